@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use hims_core_sdk::models::Appointment;
+use hims_core_sdk::modules::appointment::appointment_controller::AppointmentCreateRequest;
+use tauri::State;
+
+use super::{parse_id, CommandResult};
+use crate::state::AppState;
+
+/// Book an appointment; fails with a `conflict` when a participant is
+/// already booked at that time
+#[tauri::command]
+pub async fn book_appointment(
+    state: State<'_, AppState>,
+    appointment: AppointmentCreateRequest,
+) -> CommandResult<Appointment> {
+    let user = state.clinical_user()?;
+    let appointment = state.store.book_appointment(appointment, user).await?;
+    state.queue_changed().await;
+    Ok(appointment)
+}
+
+/// Appointments starting in `[from, to)`, of one patient when given
+#[tauri::command]
+pub async fn list_appointments(
+    state: State<'_, AppState>,
+    patient_id: Option<String>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> CommandResult<Vec<Appointment>> {
+    state.clinical_user()?;
+    let patient_id = patient_id.as_deref().map(|id| parse_id("patient_id", id)).transpose()?;
+    Ok(state.store.list_appointments(patient_id, from, to).await?)
+}
+
+#[tauri::command]
+pub async fn cancel_appointment(state: State<'_, AppState>, id: String) -> CommandResult<Appointment> {
+    let user = state.clinical_user()?;
+    let appointment = state.store.cancel_appointment(parse_id("id", &id)?, user).await?;
+    state.queue_changed().await;
+    Ok(appointment)
+}
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use hims_core_sdk::modules::sync::LocalAuditReport;
+use tauri::State;
+
+use super::CommandResult;
+use crate::state::AppState;
+
+/// Audit report of this device's activity in `[start, end)`
+#[tauri::command]
+pub async fn generate_audit_report(
+    state: State<'_, AppState>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> CommandResult<LocalAuditReport> {
+    state.user()?;
+    Ok(state.store.audit_report(start, end).await?)
+}
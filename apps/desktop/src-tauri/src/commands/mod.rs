@@ -0,0 +1,66 @@
+//! Commands the desktop frontend invokes
+//!
+//! - Session: smart card or fingerprint login, and the purpose of use
+//! - Patients: registration, lookup and edits
+//! - Appointments: booking, listing and cancellation
+//! - Records: writing, listing and finalizing medical records
+//! - Audit: audit reports
+//! - Sync: sync status, manual syncs and conflict resolution
+//!
+//! Everything is read from and written to the local SQLite store; writes
+//! are queued for the central server.
+
+pub mod appointments;
+pub mod audit;
+pub mod patients;
+pub mod records;
+pub mod session;
+pub mod sync;
+
+use hims_core_sdk::core::HimsError;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Error returned to the frontend, with a code it can branch on
+#[derive(Debug, Serialize)]
+pub struct CommandError {
+    /// `validation`, `conflict`, `stale`, `unauthenticated`, `forbidden`,
+    /// `offline` or `internal`
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<HimsError> for CommandError {
+    fn from(e: HimsError) -> Self {
+        let code = match &e {
+            HimsError::ValidationError { .. } => "validation",
+            HimsError::ConflictError { .. } => "conflict",
+            HimsError::PreconditionFailed { .. } => "stale",
+            HimsError::AuthenticationError { .. } => "unauthenticated",
+            HimsError::SecurityError { .. } => "forbidden",
+            _ => "internal",
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+pub type CommandResult<T> = Result<T, CommandError>;
+
+/// An unexpected failure, such as of the sync queue
+pub fn internal(e: impl std::fmt::Display) -> CommandError {
+    CommandError::new("internal", e.to_string())
+}
+
+/// Parse an ID passed by the frontend
+pub fn parse_id(field: &str, id: &str) -> CommandResult<Uuid> {
+    Uuid::parse_str(id).map_err(|_| CommandError::new("validation", format!("{} is not a valid ID: {}", field, id)))
+}
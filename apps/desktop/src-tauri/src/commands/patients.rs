@@ -0,0 +1,38 @@
+use hims_core_sdk::models::Patient;
+use hims_core_sdk::modules::patient::patient_controller::PatientCreateRequest;
+use tauri::State;
+
+use super::{parse_id, CommandResult};
+use crate::state::AppState;
+
+/// Register a new patient
+#[tauri::command]
+pub async fn create_patient(state: State<'_, AppState>, patient: PatientCreateRequest) -> CommandResult<Patient> {
+    let user = state.clinical_user()?;
+    let patient = state.store.register_patient(patient, user).await?;
+    state.queue_changed().await;
+    Ok(patient)
+}
+
+/// A patient by ID, or `null` when not stored on this device
+#[tauri::command]
+pub async fn get_patient(state: State<'_, AppState>, id: String) -> CommandResult<Option<Patient>> {
+    let user = state.clinical_user()?;
+    Ok(state.store.get_patient(parse_id("id", &id)?, user).await?)
+}
+
+/// Patients whose names or identifiers contain `query`
+#[tauri::command]
+pub async fn search_patients(state: State<'_, AppState>, query: String) -> CommandResult<Vec<Patient>> {
+    let user = state.clinical_user()?;
+    Ok(state.store.search_patients(&query, user).await?)
+}
+
+/// Save an edited patient, as read with its `meta`
+#[tauri::command]
+pub async fn update_patient(state: State<'_, AppState>, patient: Patient) -> CommandResult<Patient> {
+    let user = state.clinical_user()?;
+    let patient = state.store.update_patient(patient, user).await?;
+    state.queue_changed().await;
+    Ok(patient)
+}
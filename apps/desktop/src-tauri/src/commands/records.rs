@@ -0,0 +1,38 @@
+use hims_core_sdk::models::MedicalRecord;
+use hims_core_sdk::modules::medical_record::medical_record_controller::MedicalRecordCreateRequest;
+use tauri::State;
+
+use super::{parse_id, CommandResult};
+use crate::state::AppState;
+
+/// Write a medical record, free text or from a built-in note template
+#[tauri::command]
+pub async fn create_medical_record(
+    state: State<'_, AppState>,
+    record: MedicalRecordCreateRequest,
+) -> CommandResult<MedicalRecord> {
+    let user = state.clinical_user()?;
+    let record = state.store.create_medical_record(record, user).await?;
+    state.queue_changed().await;
+    Ok(record)
+}
+
+/// A patient's medical records, newest first
+#[tauri::command]
+pub async fn list_medical_records(state: State<'_, AppState>, patient_id: String) -> CommandResult<Vec<MedicalRecord>> {
+    let user = state.clinical_user()?;
+    Ok(state.store.list_medical_records(parse_id("patient_id", &patient_id)?, user).await?)
+}
+
+/// Finalize a preliminary record, from the version it was read at
+#[tauri::command]
+pub async fn finalize_medical_record(
+    state: State<'_, AppState>,
+    id: String,
+    version_id: String,
+) -> CommandResult<MedicalRecord> {
+    let user = state.clinical_user()?;
+    let record = state.store.finalize_medical_record(parse_id("id", &id)?, &version_id, user).await?;
+    state.queue_changed().await;
+    Ok(record)
+}
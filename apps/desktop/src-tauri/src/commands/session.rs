@@ -0,0 +1,57 @@
+use hims_core_sdk::modules::auth::workstation_service::WorkstationChallenge;
+use hims_core_sdk::modules::auth::SessionClient;
+use hims_core_sdk::modules::authorization::PurposeOfUse;
+use hims_core_sdk::security::CredentialAssertion;
+use tauri::State;
+
+use super::{parse_id, CommandError, CommandResult};
+use crate::state::AppState;
+
+/// What the desktop app reports itself as when it logs a user in
+const USER_AGENT: &str = concat!("open-hims-desktop/", env!("CARGO_PKG_VERSION"));
+
+/// A one-time challenge for the user's smart card or fingerprint reader to
+/// answer, from the central server
+#[tauri::command]
+pub fn begin_login(state: State<'_, AppState>) -> CommandResult<WorkstationChallenge> {
+    Ok(state.login()?.begin_challenge()?)
+}
+
+/// Act as the user whose smart card or fingerprint answered the login
+/// challenge, for a purpose of use such as `treatment` when one is declared.
+/// The answer is verified against the credentials enrolled on the central
+/// server; the frontend never names the user itself.
+#[tauri::command]
+pub async fn start_session(
+    state: State<'_, AppState>,
+    assertion: CredentialAssertion,
+    purpose_of_use: Option<String>,
+) -> CommandResult<()> {
+    let purpose_of_use = purpose_of_use.as_deref().map(parse_purpose).transpose()?;
+    let client = SessionClient {
+        user_agent: Some(USER_AGENT.to_string()),
+        ..SessionClient::default()
+    };
+    let (user, _) = state.login()?.login(&assertion, &client).await?;
+    state.sign_in(parse_id("user_id", &user.id)?, purpose_of_use);
+    Ok(())
+}
+
+/// Declare why patient data is being accessed, such as `treatment` or
+/// `payment`; patient and appointment commands fail until one is
+#[tauri::command]
+pub fn set_purpose_of_use(state: State<'_, AppState>, purpose_of_use: String) -> CommandResult<()> {
+    state.user()?;
+    state.set_purpose_of_use(Some(parse_purpose(&purpose_of_use)?));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn end_session(state: State<'_, AppState>) {
+    state.sign_out();
+}
+
+fn parse_purpose(purpose: &str) -> CommandResult<PurposeOfUse> {
+    PurposeOfUse::parse(purpose)
+        .ok_or_else(|| CommandError::new("validation", format!("Unknown purpose of use: {}", purpose)))
+}
@@ -0,0 +1,49 @@
+use hims_core_sdk::modules::sync::{ConflictResolution, SyncConflict, SyncReport, SyncStatus};
+use tauri::State;
+
+use super::{internal, parse_id, CommandError, CommandResult};
+use crate::state::AppState;
+
+/// Whether a sync pass is running and how far it got, when the app last
+/// synced and how many writes and conflicts wait
+#[tauri::command]
+pub async fn sync_status(state: State<'_, AppState>) -> CommandResult<SyncStatus> {
+    let status = match &state.sync {
+        Some(engine) => engine.status().await,
+        None => SyncStatus::read(state.store.outbox()).await,
+    };
+    status.map_err(internal)
+}
+
+/// Replay queued writes now rather than at the next background pass
+#[tauri::command]
+pub async fn sync_now(state: State<'_, AppState>) -> CommandResult<SyncReport> {
+    state.user()?;
+    let engine = state
+        .sync
+        .as_ref()
+        .ok_or_else(|| CommandError::new("offline", "No sync server is configured"))?;
+    engine.sync_once().await.map_err(internal)
+}
+
+/// Writes the server refused because the resource changed there, oldest
+/// first, each with the local write and the server's state to compare
+#[tauri::command]
+pub async fn list_sync_conflicts(state: State<'_, AppState>) -> CommandResult<Vec<SyncConflict>> {
+    state.user()?;
+    state.store.outbox().conflicts().await.map_err(internal)
+}
+
+/// Settle a conflict, as `{ strategy: 'keep_server' | 'keep_local' }` or
+/// `{ strategy: 'merged', payload }`
+#[tauri::command]
+pub async fn resolve_sync_conflict(
+    state: State<'_, AppState>,
+    id: String,
+    resolution: ConflictResolution,
+) -> CommandResult<()> {
+    let user = state.user()?;
+    state.store.resolve_conflict(parse_id("id", &id)?, resolution, user).await?;
+    state.queue_changed().await;
+    Ok(())
+}
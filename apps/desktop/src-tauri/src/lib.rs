@@ -1,8 +1,96 @@
-// Minimal Tauri app - just a UI wrapper for HIMS React app
+// Tauri shell for the HIMS React app, with patients, appointments and
+// records kept in a local SQLite database so it works offline. The database
+// is encrypted with SQLCipher under a key kept in the OS keychain, and
+// writes are replayed to the central server in the background.
+mod commands;
+mod state;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use hims_core_sdk::modules::auth::{AuthService, WorkstationConfig, WorkstationService};
+use hims_core_sdk::modules::sync::{DatabaseKey, LocalStore, SyncEngine};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::{appointments, audit, patients, records, session, sync};
+use crate::state::AppState;
+
+/// Database file in the app's data directory
+const DATABASE_FILE: &str = "hims.db";
+/// PostgreSQL URL of the central server; without it the app stays offline
+const SYNC_SERVER_ENV: &str = "HIMS_SYNC_DATABASE_URL";
+/// How often queued writes are replayed
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+/// Event carrying the `SyncStatus` whenever it changes
+const SYNC_STATUS_EVENT: &str = "sync-status";
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            let database_url = format!("sqlite://{}", data_dir.join(DATABASE_FILE).display());
+            // Keyed per install, under the app's identifier
+            let key = DatabaseKey::from_keychain(&app.config().identifier)?;
+            let store = tauri::async_runtime::block_on(LocalStore::connect_encrypted(&database_url, &key))?;
+
+            let sync = match std::env::var(SYNC_SERVER_ENV) {
+                // Within the runtime, which the server pool's upkeep tasks run on
+                Ok(url) => Some(Arc::new(tauri::async_runtime::block_on(async {
+                    SyncEngine::connect_lazy(store.outbox().clone(), &url)
+                })?)),
+                Err(_) => None,
+            };
+            // Logins are checked against the users and credentials enrolled
+            // on the central server
+            let login = sync.as_ref().map(|engine| {
+                let server = engine.server().clone();
+                let auth_service = Arc::new(AuthService::new(server.clone()));
+                Arc::new(WorkstationService::new(server, auth_service, WorkstationConfig::from_env()))
+            });
+            if let Some(engine) = &sync {
+                start_sync(app.handle().clone(), engine.clone());
+            }
+            app.manage(AppState::new(store, sync, login));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            session::begin_login,
+            session::start_session,
+            session::end_session,
+            session::set_purpose_of_use,
+            patients::create_patient,
+            patients::get_patient,
+            patients::search_patients,
+            patients::update_patient,
+            appointments::book_appointment,
+            appointments::list_appointments,
+            appointments::cancel_appointment,
+            records::create_medical_record,
+            records::list_medical_records,
+            records::finalize_medical_record,
+            audit::generate_audit_report,
+            sync::sync_status,
+            sync::sync_now,
+            sync::list_sync_conflicts,
+            sync::resolve_sync_conflict,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Sync in the background and forward each status change to the frontend
+fn start_sync(app: AppHandle, engine: Arc<SyncEngine>) {
+    let mut status = engine.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let _ = engine.spawn(SYNC_INTERVAL).await;
+    });
+    tauri::async_runtime::spawn(async move {
+        while status.changed().await.is_ok() {
+            let current = status.borrow_and_update().clone();
+            let _ = app.emit(SYNC_STATUS_EVENT, current);
+        }
+    });
+}
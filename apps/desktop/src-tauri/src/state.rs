@@ -0,0 +1,94 @@
+use std::sync::{Arc, RwLock};
+
+use hims_core_sdk::modules::auth::WorkstationService;
+use hims_core_sdk::modules::authorization::PurposeOfUse;
+use hims_core_sdk::modules::sync::{LocalStore, SyncEngine};
+use uuid::Uuid;
+
+use crate::commands::CommandError;
+
+/// State shared by every command
+pub struct AppState {
+    pub store: LocalStore,
+    /// Replays local writes to the central server; `None` when no server is
+    /// configured
+    pub sync: Option<Arc<SyncEngine>>,
+    /// Checks smart card and fingerprint logins against the central server;
+    /// `None` when no server is configured, and no one can sign in
+    login: Option<Arc<WorkstationService>>,
+    /// User the login screen signed in; every command acts as them
+    user: RwLock<Option<Uuid>>,
+    /// Why the user is accessing patient data, as the server's
+    /// `X-Purpose-Of-Use` header declares it
+    purpose_of_use: RwLock<Option<PurposeOfUse>>,
+}
+
+impl AppState {
+    pub fn new(store: LocalStore, sync: Option<Arc<SyncEngine>>, login: Option<Arc<WorkstationService>>) -> Self {
+        Self {
+            store,
+            sync,
+            login,
+            user: RwLock::new(None),
+            purpose_of_use: RwLock::new(None),
+        }
+    }
+
+    /// Tell the sync engine the queue changed, so the UI's counts update
+    /// without waiting for the next background pass
+    pub async fn queue_changed(&self) {
+        if let Some(engine) = &self.sync {
+            // The background pass reads them again anyway
+            let _ = engine.refresh_status().await;
+        }
+    }
+
+    /// The service verifying logins
+    pub fn login(&self) -> Result<&WorkstationService, CommandError> {
+        self.login
+            .as_deref()
+            .ok_or_else(|| CommandError::new("offline", "Signing in needs the central server"))
+    }
+
+    /// Act as a user a verified login returned
+    pub fn sign_in(&self, user_id: Uuid, purpose_of_use: Option<PurposeOfUse>) {
+        if let Ok(mut user) = self.user.write() {
+            *user = Some(user_id);
+        }
+        self.set_purpose_of_use(purpose_of_use);
+    }
+
+    pub fn sign_out(&self) {
+        if let Ok(mut user) = self.user.write() {
+            *user = None;
+        }
+        self.set_purpose_of_use(None);
+    }
+
+    pub fn set_purpose_of_use(&self, purpose_of_use: Option<PurposeOfUse>) {
+        if let Ok(mut purpose) = self.purpose_of_use.write() {
+            *purpose = purpose_of_use;
+        }
+    }
+
+    /// The signed-in user, who audit entries are recorded against
+    pub fn user(&self) -> Result<Uuid, CommandError> {
+        self.user
+            .read()
+            .ok()
+            .and_then(|user| *user)
+            .ok_or_else(|| CommandError::new("unauthenticated", "Sign in first"))
+    }
+
+    /// The signed-in user, once they have declared why they are accessing
+    /// patient data; the server denies patient data without a purpose
+    pub fn clinical_user(&self) -> Result<Uuid, CommandError> {
+        let user = self.user()?;
+        self.purpose_of_use
+            .read()
+            .ok()
+            .and_then(|purpose| *purpose)
+            .ok_or_else(|| CommandError::new("forbidden", "Declare a purpose of use to access patient data"))?;
+        Ok(user)
+    }
+}
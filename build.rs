@@ -1,3 +1,11 @@
 fn main() {
+    // Bindings for the mobile apps; browser builds leave them out
+    #[cfg(feature = "ffi")]
     uniffi::generate_scaffolding("src/hims_core_sdk.udl").unwrap();
-}
\ No newline at end of file
+
+    // Clients are generated too, for Rust services calling another server
+    #[cfg(feature = "grpc")]
+    tonic_build::configure()
+        .compile_protos(&["proto/hims/v1/hims.proto"], &["proto"])
+        .unwrap();
+}
@@ -1,5 +1,5 @@
 -- Create authorization tables migration
--- Migration: 2024_create_authorization_tables.sql
+-- Migration: 20231017000004_authorization_tables.sql
 
 -- Create authorization_relations table for storing relationship tuples
 CREATE TABLE authorization_relations (
@@ -198,11 +198,7 @@ CREATE TABLE authorization_session_context (
     
     -- Foreign key constraints
     CONSTRAINT fk_authorization_session_context_user_id 
-        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
-    CONSTRAINT fk_authorization_session_context_department_id 
-        FOREIGN KEY (department_id) REFERENCES departments(id) ON DELETE SET NULL,
-    CONSTRAINT fk_authorization_session_context_location_id 
-        FOREIGN KEY (location_id) REFERENCES locations(id) ON DELETE SET NULL
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
 );
 
 -- Indexes for session context
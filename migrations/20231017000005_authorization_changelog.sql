@@ -0,0 +1,48 @@
+-- Authorization changelog for Watch API consumers
+-- Migration: 20231017000005_authorization_changelog.sql
+
+-- Every relationship tuple write is recorded with a monotonically increasing
+-- revision. External caches and downstream services replay this log from the
+-- last revision (zookie) they have seen.
+CREATE TABLE authorization_changelog (
+    revision BIGSERIAL PRIMARY KEY,
+    operation VARCHAR(20) NOT NULL CHECK (operation IN ('added', 'removed')),
+    resource_type VARCHAR(100) NOT NULL,
+    resource_id UUID NOT NULL,
+    relation VARCHAR(100) NOT NULL,
+    subject_type VARCHAR(100) NOT NULL,
+    subject_id UUID NOT NULL,
+    changed_by UUID,
+    changed_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_authorization_changelog_changed_at
+    ON authorization_changelog (changed_at);
+
+CREATE INDEX idx_authorization_changelog_resource
+    ON authorization_changelog (resource_type, resource_id);
+
+-- Record tuple additions and removals (removals are soft deletes)
+CREATE OR REPLACE FUNCTION record_authorization_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' AND NEW.is_active THEN
+        INSERT INTO authorization_changelog (
+            operation, resource_type, resource_id, relation, subject_type, subject_id, changed_by
+        ) VALUES (
+            'added', NEW.resource_type, NEW.resource_id, NEW.relation, NEW.subject_type, NEW.subject_id, NEW.created_by
+        );
+    ELSIF TG_OP = 'UPDATE' AND OLD.is_active AND NOT NEW.is_active THEN
+        INSERT INTO authorization_changelog (
+            operation, resource_type, resource_id, relation, subject_type, subject_id
+        ) VALUES (
+            'removed', NEW.resource_type, NEW.resource_id, NEW.relation, NEW.subject_type, NEW.subject_id
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER authorization_relations_changelog
+    AFTER INSERT OR UPDATE OF is_active ON authorization_relations
+    FOR EACH ROW EXECUTE FUNCTION record_authorization_change();
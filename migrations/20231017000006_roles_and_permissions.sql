@@ -0,0 +1,51 @@
+-- Role and permission administration
+-- Migration: 20231017000006_roles_and_permissions.sql
+
+-- Roles are named permission bundles. Each permission is an authorization
+-- Action name (read, prescribe, manage_roles, ...).
+CREATE TABLE roles (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(100) UNIQUE NOT NULL,
+    description TEXT,
+    permissions JSONB NOT NULL DEFAULT '[]',
+    is_system BOOLEAN NOT NULL DEFAULT false,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Users holding a role
+CREATE TABLE role_members (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    assigned_by UUID REFERENCES users(id),
+    assigned_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE,
+    is_active BOOLEAN NOT NULL DEFAULT true
+);
+
+CREATE UNIQUE INDEX idx_role_members_unique
+    ON role_members (role_id, user_id)
+    WHERE is_active = true;
+
+CREATE INDEX idx_roles_name ON roles(name);
+CREATE INDEX idx_roles_active ON roles(is_active);
+CREATE INDEX idx_role_members_user ON role_members(user_id);
+CREATE INDEX idx_role_members_role ON role_members(role_id);
+
+CREATE TRIGGER update_roles_updated_at BEFORE UPDATE ON roles FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Built-in roles. System roles cannot be modified or deleted through the API.
+INSERT INTO roles (name, description, permissions, is_system) VALUES
+    ('system_administrator', 'Full administrative access',
+     '["configure", "manage_users", "manage_roles", "manage_permissions", "backup_data", "restore_data", "audit"]', true),
+    ('physician', 'Clinical access for treating physicians',
+     '["read", "write", "update", "search", "prescribe", "diagnose", "order_test", "view_results", "modify_treatment", "schedule"]', true),
+    ('nurse', 'Clinical access for nursing staff',
+     '["read", "update", "search", "schedule", "view_results"]', true),
+    ('receptionist', 'Front desk scheduling access',
+     '["read", "search", "schedule", "cancel"]', true),
+    ('billing_clerk', 'Billing and payment processing',
+     '["view_billing", "process_payment", "adjust_billing"]', true);
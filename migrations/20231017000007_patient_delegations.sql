@@ -0,0 +1,33 @@
+-- Proxy and guardian delegation of patient record access
+-- Migration: 20231017000007_patient_delegations.sql
+
+-- Each delegation is mirrored by a ProxyAccess/Guardian relationship tuple in
+-- authorization_relations. This table holds the workflow state: scope limits,
+-- identity proofing evidence and revocation history.
+CREATE TABLE patient_delegations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    proxy_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    relation VARCHAR(50) NOT NULL CHECK (relation IN ('proxy_access', 'guardian')),
+    scope JSONB NOT NULL DEFAULT '[]', -- Array of permitted action names
+    is_parental BOOLEAN NOT NULL DEFAULT false,
+    identity_proofing JSONB NOT NULL, -- Method, document reference, verifier
+    status VARCHAR(30) NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'revoked', 'expired', 'revoked_at_majority')),
+    granted_by UUID NOT NULL REFERENCES users(id),
+    granted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE,
+    revoked_by UUID REFERENCES users(id),
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    revocation_reason TEXT
+);
+
+CREATE UNIQUE INDEX idx_patient_delegations_unique
+    ON patient_delegations (patient_id, proxy_user_id, relation)
+    WHERE status = 'active';
+
+CREATE INDEX idx_patient_delegations_patient ON patient_delegations(patient_id);
+CREATE INDEX idx_patient_delegations_proxy ON patient_delegations(proxy_user_id);
+CREATE INDEX idx_patient_delegations_parental
+    ON patient_delegations(patient_id)
+    WHERE is_parental = true AND status = 'active';
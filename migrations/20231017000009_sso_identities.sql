@@ -0,0 +1,19 @@
+-- Single sign-on identity links
+-- Migration: 20231017000009_sso_identities.sql
+
+-- Links an external IdP subject to a HIMS user. Users created through SSO
+-- have no local password.
+CREATE TABLE sso_identities (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    provider VARCHAR(100) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email VARCHAR(255),
+    claims JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_sso_identity UNIQUE (provider, subject)
+);
+
+CREATE INDEX idx_sso_identities_user ON sso_identities(user_id);
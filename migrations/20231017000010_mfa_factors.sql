@@ -0,0 +1,28 @@
+-- Multi-factor authentication factors
+-- Migration: 20231017000010_mfa_factors.sql
+
+-- TOTP secrets and WebAuthn credentials enrolled by a user. A factor only
+-- counts once confirmed (first successful code or completed registration).
+CREATE TABLE mfa_factors (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id VARCHAR(255) NOT NULL,
+    factor_type VARCHAR(20) NOT NULL CHECK (factor_type IN ('totp', 'webauthn')),
+    name VARCHAR(255),
+    totp_secret VARCHAR(64), -- Base32 shared secret (totp only)
+    last_used_step BIGINT, -- Last accepted TOTP step, for replay prevention
+    credential_id TEXT, -- Base64url credential ID (webauthn only)
+    public_key BYTEA, -- Uncompressed P-256 point (webauthn only)
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    confirmed_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+
+    CONSTRAINT unique_webauthn_credential UNIQUE (credential_id)
+);
+
+CREATE INDEX idx_mfa_factors_user ON mfa_factors(user_id) WHERE is_active = true;
+
+-- Step-up re-verification needs to know when MFA last succeeded
+ALTER TABLE authorization_session_context
+    ADD COLUMN mfa_verified_at TIMESTAMP WITH TIME ZONE;
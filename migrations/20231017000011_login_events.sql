@@ -0,0 +1,25 @@
+-- Login events for adaptive authentication
+-- Migration: 20231017000011_login_events.sql
+
+-- One row per login attempt. Successful logins provide the device and
+-- location history used to score new logins; failures feed attempt counts.
+CREATE TABLE login_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    username VARCHAR(255) NOT NULL,
+    session_id VARCHAR(255),
+    success BOOLEAN NOT NULL,
+    ip_address INET,
+    user_agent TEXT,
+    device_hash VARCHAR(64),
+    country VARCHAR(2),
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    risk_score REAL NOT NULL DEFAULT 0.0,
+    risk_signals JSONB NOT NULL DEFAULT '[]',
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_login_events_user ON login_events(user_id, occurred_at DESC);
+CREATE INDEX idx_login_events_username ON login_events(username, occurred_at DESC);
+CREATE INDEX idx_login_events_device ON login_events(user_id, device_hash) WHERE success = true;
@@ -0,0 +1,19 @@
+-- Password policy, lockout and forced reset
+-- Migration: 20231017000012_credential_hygiene.sql
+
+ALTER TABLE users
+    ADD COLUMN failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN lockout_count INTEGER NOT NULL DEFAULT 0, -- Successive lockouts, drives progressive duration
+    ADD COLUMN locked_until TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN password_changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    ADD COLUMN must_reset_password BOOLEAN NOT NULL DEFAULT false;
+
+-- Previous password hashes, used to prevent reuse
+CREATE TABLE password_history (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    password_hash VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_password_history_user ON password_history(user_id, created_at DESC);
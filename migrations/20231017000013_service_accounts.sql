@@ -0,0 +1,54 @@
+-- Service accounts and API keys for system integrations
+-- Migration: 20231017000013_service_accounts.sql
+
+-- Non-human principals such as HL7 interface engines. Scopes are
+-- authorization Action names the account may exercise.
+CREATE TABLE service_accounts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(100) UNIQUE NOT NULL,
+    description TEXT,
+    scopes JSONB NOT NULL DEFAULT '[]',
+    rate_limit_per_minute INTEGER NOT NULL DEFAULT 600,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- API keys are stored as SHA-256 hashes; only the prefix is kept in clear
+-- so keys can be identified in listings and logs.
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    service_account_id UUID NOT NULL REFERENCES service_accounts(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(64) UNIQUE NOT NULL,
+    scopes JSONB, -- Optional narrowing of the account scopes
+    rate_limit_per_minute INTEGER, -- Overrides the account limit when set
+    expires_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    rotated_from UUID REFERENCES api_keys(id),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    last_used_ip INET,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Per-request usage trail for API keys
+CREATE TABLE api_key_usage (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    api_key_id UUID NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    service_account_id UUID NOT NULL REFERENCES service_accounts(id) ON DELETE CASCADE,
+    method VARCHAR(10) NOT NULL,
+    endpoint TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    ip_address INET,
+    used_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_service_accounts_active ON service_accounts(is_active);
+CREATE INDEX idx_api_keys_account ON api_keys(service_account_id);
+CREATE INDEX idx_api_key_usage_key ON api_key_usage(api_key_id, used_at DESC);
+CREATE INDEX idx_api_key_usage_account ON api_key_usage(service_account_id, used_at DESC);
+
+CREATE TRIGGER update_service_accounts_updated_at BEFORE UPDATE ON service_accounts FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
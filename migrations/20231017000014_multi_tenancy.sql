@@ -0,0 +1,115 @@
+-- Multi-tenancy with row-level-security isolation
+-- Migration: 20231017000014_multi_tenancy.sql
+
+-- Tenants hosted by this deployment. Shared tenants are isolated by
+-- row-level security on tenant_id; schema tenants additionally get their
+-- own copy of the tenant-scoped tables.
+CREATE TABLE tenants (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    slug VARCHAR(63) UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    domains TEXT[] NOT NULL DEFAULT '{}',
+    isolation VARCHAR(20) NOT NULL DEFAULT 'shared',
+    schema_name VARCHAR(63) UNIQUE,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_isolation CHECK (isolation IN ('shared', 'schema')),
+    CONSTRAINT schema_isolation_has_schema CHECK (isolation = 'shared' OR schema_name IS NOT NULL)
+);
+
+CREATE INDEX idx_tenants_domains ON tenants USING GIN (domains);
+
+CREATE TRIGGER update_tenants_updated_at BEFORE UPDATE ON tenants FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Existing data belongs to the default tenant
+INSERT INTO tenants (id, slug, name)
+VALUES ('00000000-0000-0000-0000-000000000001', 'default', 'Default Tenant');
+
+-- Tenant of the current connection, set by the application on every
+-- acquire. Falls back to the default tenant when unset.
+CREATE OR REPLACE FUNCTION current_tenant_id() RETURNS UUID AS $$
+    SELECT COALESCE(
+        NULLIF(current_setting('app.tenant_id', true), '')::uuid,
+        '00000000-0000-0000-0000-000000000001'::uuid
+    )
+$$ LANGUAGE sql STABLE;
+
+-- Platform maintenance jobs may read across tenants
+CREATE OR REPLACE FUNCTION tenant_bypass() RETURNS BOOLEAN AS $$
+    SELECT COALESCE(current_setting('app.bypass_tenant', true), 'off') = 'on'
+$$ LANGUAGE sql STABLE;
+
+-- Add tenant_id with row-level security to every tenant-owned table.
+-- FORCE applies the policy to the table owner too; superusers still bypass.
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOREACH t IN ARRAY ARRAY[
+        'organizations', 'users', 'patients', 'appointments', 'medical_records', 'audit_logs',
+        'practitioners', 'encounters', 'observations', 'medication_requests', 'diagnostic_reports',
+        'authorization_changelog', 'roles', 'role_members', 'patient_delegations',
+        'refresh_tokens', 'revoked_access_tokens', 'sso_identities', 'mfa_factors', 'login_events',
+        'password_history', 'service_accounts', 'api_keys', 'api_key_usage',
+        'authorization_relations', 'authorization_policies', 'authorization_audit_log',
+        'authorization_emergency_access', 'authorization_session_context'
+    ]
+    LOOP
+        EXECUTE format(
+            'ALTER TABLE %I ADD COLUMN tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id)', t);
+        EXECUTE format('CREATE INDEX idx_%s_tenant ON %I (tenant_id)', t, t);
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', t);
+        EXECUTE format('ALTER TABLE %I FORCE ROW LEVEL SECURITY', t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I USING (tenant_bypass() OR tenant_id = current_tenant_id()) WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id())', t);
+    END LOOP;
+END $$;
+
+-- Names that were globally unique are now unique per tenant
+ALTER TABLE users DROP CONSTRAINT users_username_key;
+ALTER TABLE users DROP CONSTRAINT users_email_key;
+ALTER TABLE users ADD CONSTRAINT users_tenant_username_key UNIQUE (tenant_id, username);
+ALTER TABLE users ADD CONSTRAINT users_tenant_email_key UNIQUE (tenant_id, email);
+
+ALTER TABLE organizations DROP CONSTRAINT organizations_identifier_key;
+ALTER TABLE organizations ADD CONSTRAINT organizations_tenant_identifier_key UNIQUE (tenant_id, identifier);
+
+ALTER TABLE roles DROP CONSTRAINT roles_name_key;
+ALTER TABLE roles ADD CONSTRAINT roles_tenant_name_key UNIQUE (tenant_id, name);
+
+ALTER TABLE service_accounts DROP CONSTRAINT service_accounts_name_key;
+ALTER TABLE service_accounts ADD CONSTRAINT service_accounts_tenant_name_key UNIQUE (tenant_id, name);
+
+ALTER TABLE authorization_policies DROP CONSTRAINT authorization_policies_name_key;
+ALTER TABLE authorization_policies ADD CONSTRAINT authorization_policies_tenant_name_key UNIQUE (tenant_id, name);
+
+-- Relationship tuples are scoped to their tenant
+DROP INDEX idx_authorization_relations_unique;
+CREATE UNIQUE INDEX idx_authorization_relations_unique
+    ON authorization_relations (tenant_id, resource_type, resource_id, relation, subject_type, subject_id)
+    WHERE is_active = true;
+
+-- Create a dedicated schema holding copies of the tenant-scoped tables for
+-- schema-per-tenant isolation. Connections for the tenant put the schema
+-- first on search_path, so unqualified queries resolve to it.
+CREATE OR REPLACE FUNCTION provision_tenant_schema(p_schema TEXT) RETURNS VOID AS $$
+DECLARE
+    t TEXT;
+BEGIN
+    EXECUTE format('CREATE SCHEMA IF NOT EXISTS %I', p_schema);
+    FOR t IN
+        SELECT c.relname FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = 'public' AND c.relkind = 'r' AND c.relrowsecurity
+    LOOP
+        EXECUTE format('CREATE TABLE IF NOT EXISTS %I.%I (LIKE public.%I INCLUDING ALL)', p_schema, t, t);
+        EXECUTE format('ALTER TABLE %I.%I ENABLE ROW LEVEL SECURITY', p_schema, t);
+        EXECUTE format('ALTER TABLE %I.%I FORCE ROW LEVEL SECURITY', p_schema, t);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I.%I USING (tenant_bypass() OR tenant_id = current_tenant_id()) WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id())',
+            p_schema, t);
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,9 @@
+-- Compliance violation audit events
+-- Migration: 20231017000015_compliance_audit_events.sql
+
+-- Data residency and other compliance checks record their violations in the
+-- audit trail alongside access events.
+ALTER TABLE audit_logs DROP CONSTRAINT valid_event_type;
+ALTER TABLE audit_logs ADD CONSTRAINT valid_event_type CHECK (
+    event_type IN ('patient-access', 'data-modification', 'authentication', 'export', 'system-access', 'compliance-violation')
+);
@@ -0,0 +1,111 @@
+-- FHIR resource version history with change provenance
+-- Migration: 20231017000016_resource_history.sql
+
+-- Every version of every clinical resource, as it was stored. Rows are
+-- written by trigger and never changed, so a record can be reconstructed as
+-- it stood at any point for medico-legal review.
+CREATE TABLE resource_history (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    resource_type VARCHAR(50) NOT NULL,
+    resource_id UUID NOT NULL,
+    version_id VARCHAR(64) NOT NULL,
+    operation VARCHAR(10) NOT NULL,
+    resource JSONB NOT NULL,
+    -- Provenance: who made the change, when and why
+    changed_by UUID,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    change_reason TEXT,
+
+    CONSTRAINT valid_operation CHECK (operation IN ('create', 'update', 'delete'))
+);
+
+CREATE INDEX idx_resource_history_resource ON resource_history (resource_type, resource_id, changed_at DESC);
+CREATE INDEX idx_resource_history_version ON resource_history (resource_type, resource_id, version_id);
+CREATE INDEX idx_resource_history_changed_by ON resource_history (changed_by);
+CREATE INDEX idx_resource_history_tenant ON resource_history (tenant_id);
+
+ALTER TABLE resource_history ENABLE ROW LEVEL SECURITY;
+ALTER TABLE resource_history FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON resource_history
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- History is append-only
+CREATE OR REPLACE FUNCTION reject_history_change() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'resource_history is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER resource_history_append_only
+    BEFORE UPDATE OR DELETE ON resource_history
+    FOR EACH ROW EXECUTE FUNCTION reject_history_change();
+
+-- Snapshot a resource row. The application sets app.user_id and
+-- app.change_reason on the connection for the request making the change.
+-- TG_ARGV[0] is the FHIR resource type.
+CREATE OR REPLACE FUNCTION record_resource_history() RETURNS TRIGGER AS $$
+DECLARE
+    new_row JSONB := to_jsonb(NEW);
+    op VARCHAR(10) := 'update';
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        op := 'create';
+    ELSIF new_row->>'deleted_at' IS NOT NULL OR new_row->>'active' = 'false' THEN
+        -- Compare with the last recorded version rather than OLD, since the
+        -- deletion may have been written before the version was bumped
+        IF COALESCE((
+            SELECT operation FROM resource_history
+            WHERE resource_type = TG_ARGV[0] AND resource_id = NEW.id
+            ORDER BY id DESC LIMIT 1
+        ), '') <> 'delete' THEN
+            op := 'delete';
+        END IF;
+    END IF;
+
+    INSERT INTO resource_history (
+        tenant_id, resource_type, resource_id, version_id, operation, resource,
+        changed_by, change_reason
+    ) VALUES (
+        NEW.tenant_id, TG_ARGV[0], NEW.id, COALESCE(NEW.meta->>'version_id', '1'), op, new_row,
+        NULLIF(current_setting('app.user_id', true), '')::uuid,
+        NULLIF(current_setting('app.change_reason', true), '')
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- A new version exists when the row is created or its version changes;
+-- writes that leave the version alone (e.g. sync replay before stamping)
+-- are part of the version that follows.
+CREATE TRIGGER patients_history_insert AFTER INSERT ON patients
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('Patient');
+CREATE TRIGGER patients_history_update AFTER UPDATE ON patients
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('Patient');
+
+CREATE TRIGGER appointments_history_insert AFTER INSERT ON appointments
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('Appointment');
+CREATE TRIGGER appointments_history_update AFTER UPDATE ON appointments
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('Appointment');
+
+CREATE TRIGGER medical_records_history_insert AFTER INSERT ON medical_records
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('DocumentReference');
+CREATE TRIGGER medical_records_history_update AFTER UPDATE ON medical_records
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('DocumentReference');
+
+-- Existing resources start their history at their current version. The
+-- backfill covers every tenant, so it runs with the tenant bypass.
+SELECT set_config('app.bypass_tenant', 'on', true);
+INSERT INTO resource_history (tenant_id, resource_type, resource_id, version_id, operation, resource, changed_at)
+SELECT tenant_id, 'Patient', id, COALESCE(meta->>'version_id', '1'), 'create', to_jsonb(patients), NOW()
+FROM patients;
+INSERT INTO resource_history (tenant_id, resource_type, resource_id, version_id, operation, resource, changed_at)
+SELECT tenant_id, 'Appointment', id, COALESCE(meta->>'version_id', '1'), 'create', to_jsonb(appointments), NOW()
+FROM appointments;
+INSERT INTO resource_history (tenant_id, resource_type, resource_id, version_id, operation, resource, changed_at)
+SELECT tenant_id, 'DocumentReference', id, COALESCE(meta->>'version_id', '1'), 'create', to_jsonb(medical_records), NOW()
+FROM medical_records;
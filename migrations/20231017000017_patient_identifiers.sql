@@ -0,0 +1,8 @@
+-- Patient business identifiers
+-- Migration: 20231017000017_patient_identifiers.sql
+
+-- FHIR Patient.identifier (MRN, national health ID, ...) as an array of
+-- {use_type, system, value}. Conditional create/update/delete match on it.
+ALTER TABLE patients ADD COLUMN identifier JSONB NOT NULL DEFAULT '[]';
+
+CREATE INDEX idx_patients_identifier ON patients USING GIN (identifier jsonb_path_ops);
@@ -0,0 +1,59 @@
+-- Patient search parameter indexes
+-- Migration: 20231017000018_patient_search_indexes.sql
+
+-- FHIR string search (name, address-city) matches any part of a value,
+-- case-insensitively, by prefix or substring. The functions below flatten
+-- the JSONB columns into the text those searches run against so the
+-- expressions can be indexed.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Every part of every HumanName: text, family, given, prefix, suffix
+CREATE OR REPLACE FUNCTION patient_name_parts(names JSONB)
+RETURNS TEXT[] AS $$
+    WITH n AS (
+        SELECT value AS name FROM jsonb_array_elements(
+            CASE WHEN jsonb_typeof(names) = 'array' THEN names ELSE '[]' END
+        )
+    )
+    SELECT COALESCE(array_agg(part), '{}')
+    FROM (
+        SELECT name->>'text' AS part FROM n
+        UNION ALL
+        SELECT name->>'family' FROM n
+        UNION ALL
+        SELECT jsonb_array_elements_text(
+            CASE WHEN jsonb_typeof(name->'given') = 'array' THEN name->'given' ELSE '[]' END
+            || CASE WHEN jsonb_typeof(name->'prefix') = 'array' THEN name->'prefix' ELSE '[]' END
+            || CASE WHEN jsonb_typeof(name->'suffix') = 'array' THEN name->'suffix' ELSE '[]' END
+        ) FROM n
+    ) parts
+    WHERE part IS NOT NULL AND part <> ''
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Lowercased name parts, each preceded by a space, so that
+-- `LIKE '% smi%'` is a starts-with match on any part
+CREATE OR REPLACE FUNCTION patient_name_search(names JSONB)
+RETURNS TEXT AS $$
+    SELECT COALESCE(' ' || lower(array_to_string(patient_name_parts(names), ' ')), '')
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- City of every address
+CREATE OR REPLACE FUNCTION patient_address_cities(addresses JSONB)
+RETURNS TEXT[] AS $$
+    SELECT COALESCE(array_agg(a->>'city'), '{}')
+    FROM jsonb_array_elements(
+        CASE WHEN jsonb_typeof(addresses) = 'array' THEN addresses ELSE '[]' END
+    ) a
+    WHERE a->>'city' IS NOT NULL AND a->>'city' <> ''
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Lowercased cities, laid out like patient_name_search
+CREATE OR REPLACE FUNCTION patient_city_search(addresses JSONB)
+RETURNS TEXT AS $$
+    SELECT COALESCE(' ' || lower(array_to_string(patient_address_cities(addresses), ' ')), '')
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX idx_patients_name_search ON patients USING GIN (patient_name_search(name) gin_trgm_ops);
+CREATE INDEX idx_patients_name_parts ON patients USING GIN (patient_name_parts(name));
+CREATE INDEX idx_patients_city_search ON patients USING GIN (patient_city_search(address) gin_trgm_ops);
+CREATE INDEX idx_patients_address_cities ON patients USING GIN (patient_address_cities(address));
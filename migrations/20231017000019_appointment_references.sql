@@ -0,0 +1,31 @@
+-- Appointment participant references
+-- Migration: 20231017000019_appointment_references.sql
+
+-- Add the patient and practitioner columns the appointment service
+-- expects. Chained search (patient.name=...) and _include join on them.
+ALTER TABLE appointments
+ADD COLUMN IF NOT EXISTS patient_id UUID REFERENCES patients(id),
+ADD COLUMN IF NOT EXISTS practitioner_id UUID REFERENCES practitioners(id);
+
+CREATE INDEX IF NOT EXISTS idx_appointments_patient ON appointments(patient_id);
+CREATE INDEX IF NOT EXISTS idx_appointments_practitioner ON appointments(practitioner_id);
+
+-- Fill them from participant actors (`Patient/{id}`, `Practitioner/{id}`)
+-- across every tenant
+SELECT set_config('app.bypass_tenant', 'on', true);
+UPDATE appointments a
+SET patient_id = COALESCE(a.patient_id, (
+        SELECT substring(p->'actor'->>'reference' FROM 9)::uuid
+        FROM jsonb_array_elements(a.participant) p
+        WHERE p->'actor'->>'reference' ~ '^Patient/[0-9a-fA-F-]{36}$'
+            AND EXISTS (SELECT 1 FROM patients WHERE id = substring(p->'actor'->>'reference' FROM 9)::uuid)
+        LIMIT 1
+    )),
+    practitioner_id = COALESCE(a.practitioner_id, (
+        SELECT substring(p->'actor'->>'reference' FROM 14)::uuid
+        FROM jsonb_array_elements(a.participant) p
+        WHERE p->'actor'->>'reference' ~ '^Practitioner/[0-9a-fA-F-]{36}$'
+            AND EXISTS (SELECT 1 FROM practitioners WHERE id = substring(p->'actor'->>'reference' FROM 14)::uuid)
+        LIMIT 1
+    ))
+WHERE jsonb_typeof(a.participant) = 'array';
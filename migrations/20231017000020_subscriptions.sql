@@ -0,0 +1,80 @@
+-- FHIR Subscriptions (R4 Subscriptions Backport) with rest-hook and websocket channels
+-- Migration: 20231017000020_subscriptions.sql
+
+-- A client's standing request to be told about resources matching
+-- `criteria`, a search URL such as `Appointment?status=booked`.
+CREATE TABLE subscriptions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    status VARCHAR(20) NOT NULL DEFAULT 'active',
+    criteria TEXT NOT NULL,
+    resource_type VARCHAR(50) NOT NULL,
+    reason TEXT,
+    channel_type VARCHAR(20) NOT NULL,
+    -- rest-hook only: where notifications are POSTed, and extra headers
+    endpoint TEXT,
+    headers JSONB NOT NULL DEFAULT '[]',
+    -- empty, id-only or full-resource
+    content VARCHAR(20) NOT NULL DEFAULT 'id-only',
+    end_time TIMESTAMP WITH TIME ZONE,
+    error TEXT,
+    events_since_start BIGINT NOT NULL DEFAULT 0,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_subscription_status CHECK (status IN ('requested', 'active', 'error', 'off')),
+    CONSTRAINT valid_channel_type CHECK (channel_type IN ('rest-hook', 'websocket')),
+    CONSTRAINT valid_content CHECK (content IN ('empty', 'id-only', 'full-resource')),
+    CONSTRAINT rest_hook_endpoint CHECK (channel_type <> 'rest-hook' OR endpoint IS NOT NULL)
+);
+
+-- One notification per matching resource version, kept until delivered or
+-- out of retries
+CREATE TABLE subscription_notifications (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    subscription_id UUID NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+    history_id BIGINT NOT NULL REFERENCES resource_history(id),
+    event_number BIGINT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMP WITH TIME ZONE,
+
+    CONSTRAINT valid_notification_status CHECK (status IN ('pending', 'delivered', 'failed')),
+    UNIQUE (subscription_id, history_id)
+);
+
+-- How far through resource_history the dispatcher has matched. A single
+-- row, shared by all tenants, so it is not tenant-scoped.
+CREATE TABLE subscription_dispatch_state (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    last_history_id BIGINT NOT NULL
+);
+
+-- Subscriptions only see changes made after they are deployed
+INSERT INTO subscription_dispatch_state (last_history_id)
+SELECT COALESCE(MAX(id), 0) FROM resource_history;
+
+CREATE INDEX idx_subscriptions_active ON subscriptions (resource_type) WHERE status = 'active';
+CREATE INDEX idx_subscriptions_tenant ON subscriptions (tenant_id);
+CREATE INDEX idx_subscription_notifications_due ON subscription_notifications (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX idx_subscription_notifications_subscription ON subscription_notifications (subscription_id, event_number);
+CREATE INDEX idx_subscription_notifications_tenant ON subscription_notifications (tenant_id);
+
+ALTER TABLE subscriptions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE subscriptions FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON subscriptions
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE subscription_notifications ENABLE ROW LEVEL SECURITY;
+ALTER TABLE subscription_notifications FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON subscription_notifications
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER update_subscriptions_updated_at BEFORE UPDATE ON subscriptions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,69 @@
+-- Outbound HL7 v2 ADT feed delivered over MLLP
+-- Migration: 20231017000021_hl7_outbound_feed.sql
+
+-- Downstream systems (lab, radiology, billing, ...) that receive ADT
+-- messages, and which trigger events each wants
+CREATE TABLE hl7_destinations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    name VARCHAR(100) NOT NULL,
+    host VARCHAR(255) NOT NULL,
+    port INTEGER NOT NULL,
+    sending_application VARCHAR(100) NOT NULL DEFAULT 'HIMS',
+    sending_facility VARCHAR(100) NOT NULL DEFAULT '',
+    receiving_application VARCHAR(100) NOT NULL DEFAULT '',
+    receiving_facility VARCHAR(100) NOT NULL DEFAULT '',
+    events TEXT[] NOT NULL DEFAULT ARRAY['A01', 'A03', 'A04', 'A08'],
+    active BOOLEAN NOT NULL DEFAULT true,
+    -- MSH-13 of the next message queued for this destination
+    next_sequence BIGINT NOT NULL DEFAULT 1,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_hl7_port CHECK (port BETWEEN 1 AND 65535),
+    CONSTRAINT valid_hl7_events CHECK (events <@ ARRAY['A01', 'A03', 'A04', 'A08']),
+    UNIQUE (tenant_id, name)
+);
+
+-- Generated messages, sent to each destination in sequence order
+CREATE TABLE hl7_outbound_messages (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    destination_id UUID NOT NULL REFERENCES hl7_destinations(id) ON DELETE CASCADE,
+    sequence_number BIGINT NOT NULL,
+    control_id VARCHAR(20) NOT NULL,
+    event_type VARCHAR(3) NOT NULL,
+    patient_id UUID NOT NULL,
+    message TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    -- MSA-1 of the last acknowledgement received
+    ack_code VARCHAR(2),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMP WITH TIME ZONE,
+
+    CONSTRAINT valid_hl7_message_status CHECK (status IN ('pending', 'sent', 'failed')),
+    UNIQUE (destination_id, sequence_number)
+);
+
+CREATE INDEX idx_hl7_destinations_tenant ON hl7_destinations (tenant_id);
+CREATE INDEX idx_hl7_outbound_pending ON hl7_outbound_messages (destination_id, sequence_number) WHERE status = 'pending';
+CREATE INDEX idx_hl7_outbound_patient ON hl7_outbound_messages (patient_id);
+CREATE INDEX idx_hl7_outbound_tenant ON hl7_outbound_messages (tenant_id);
+
+ALTER TABLE hl7_destinations ENABLE ROW LEVEL SECURITY;
+ALTER TABLE hl7_destinations FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON hl7_destinations
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE hl7_outbound_messages ENABLE ROW LEVEL SECURITY;
+ALTER TABLE hl7_outbound_messages FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON hl7_outbound_messages
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER update_hl7_destinations_updated_at BEFORE UPDATE ON hl7_destinations FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,86 @@
+-- Interface engine message queue with retries and dead letters
+-- Migration: 20231017000022_interface_engine.sql
+
+-- A stream of interface traffic delivered in order, such as one HL7
+-- destination. Locked while its head message is being handled.
+CREATE TABLE interface_connections (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    name VARCHAR(200) NOT NULL,
+    direction VARCHAR(10) NOT NULL,
+    protocol VARCHAR(20) NOT NULL,
+    -- Paused connections keep queueing but nothing is handled
+    paused BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_interface_direction CHECK (direction IN ('inbound', 'outbound')),
+    CONSTRAINT valid_interface_protocol CHECK (protocol IN ('hl7v2', 'fhir', 'abdm')),
+    UNIQUE (tenant_id, name)
+);
+
+-- Messages, handled one at a time per connection in id order. A message
+-- out of attempts becomes a dead letter and stops holding up the rest.
+CREATE TABLE interface_messages (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    connection_id UUID NOT NULL REFERENCES interface_connections(id) ON DELETE CASCADE,
+    message_type VARCHAR(50) NOT NULL,
+    payload TEXT NOT NULL,
+    -- Whatever the handler needs besides the payload, e.g. an HL7 control ID
+    metadata JSONB NOT NULL DEFAULT '{}',
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 10,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    -- The other side's answer to the last attempt, e.g. an ACK code
+    response TEXT,
+    replays INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMP WITH TIME ZONE,
+    dead_at TIMESTAMP WITH TIME ZONE,
+
+    CONSTRAINT valid_interface_message_status CHECK (status IN ('pending', 'delivered', 'dead')),
+    CONSTRAINT valid_max_attempts CHECK (max_attempts > 0)
+);
+
+CREATE INDEX idx_interface_connections_tenant ON interface_connections (tenant_id);
+CREATE INDEX idx_interface_messages_pending ON interface_messages (connection_id, id) WHERE status = 'pending';
+CREATE INDEX idx_interface_messages_dead ON interface_messages (connection_id, id) WHERE status = 'dead';
+CREATE INDEX idx_interface_messages_tenant ON interface_messages (tenant_id);
+
+ALTER TABLE interface_connections ENABLE ROW LEVEL SECURITY;
+ALTER TABLE interface_connections FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON interface_connections
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE interface_messages ENABLE ROW LEVEL SECURITY;
+ALTER TABLE interface_messages FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON interface_messages
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER update_interface_connections_updated_at BEFORE UPDATE ON interface_connections FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- The ADT feed now queues through the interface engine, one connection per
+-- destination. Carry over what its own queue still held.
+INSERT INTO interface_connections (tenant_id, name, direction, protocol, paused)
+SELECT tenant_id, 'hl7:' || id, 'outbound', 'hl7v2', NOT active
+FROM hl7_destinations;
+
+INSERT INTO interface_messages (
+    tenant_id, connection_id, message_type, payload, metadata, status, attempts,
+    next_attempt_at, last_error, response, created_at, delivered_at, dead_at
+)
+SELECT m.tenant_id, c.id, 'ADT^' || m.event_type, m.message,
+    jsonb_build_object('control_id', m.control_id, 'sequence_number', m.sequence_number, 'patient_id', m.patient_id),
+    CASE m.status WHEN 'sent' THEN 'delivered' WHEN 'failed' THEN 'dead' ELSE 'pending' END,
+    m.attempts, m.next_attempt_at, m.last_error, m.ack_code, m.created_at, m.sent_at,
+    CASE WHEN m.status = 'failed' THEN NOW() END
+FROM hl7_outbound_messages m
+JOIN interface_connections c ON c.tenant_id = m.tenant_id AND c.name = 'hl7:' || m.destination_id
+ORDER BY m.destination_id, m.sequence_number;
+
+DROP TABLE hl7_outbound_messages;
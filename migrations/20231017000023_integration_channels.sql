@@ -0,0 +1,49 @@
+-- Integration channel registry
+-- Migration: 20231017000023_integration_channels.sql
+
+-- Endpoints the interface engine talks to: MLLP listeners, FHIR servers,
+-- SFTP drops and the ABDM gateway
+CREATE TABLE integration_channels (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    name VARCHAR(100) NOT NULL,
+    channel_type VARCHAR(20) NOT NULL,
+    direction VARCHAR(10) NOT NULL,
+    -- Where and how to connect, by channel type; never holds secrets
+    endpoint JSONB NOT NULL,
+    -- Transformation steps applied to each message, in order
+    pipeline TEXT[] NOT NULL DEFAULT '{}',
+    -- AES-256-GCM sealed JSON object of secrets; only their names are readable
+    credentials BYTEA,
+    credential_keys TEXT[] NOT NULL DEFAULT '{}',
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_channel_type CHECK (channel_type IN ('mllp', 'fhir', 'sftp', 'abdm')),
+    CONSTRAINT valid_channel_direction CHECK (direction IN ('inbound', 'outbound')),
+    UNIQUE (tenant_id, name)
+);
+
+CREATE INDEX idx_integration_channels_tenant ON integration_channels (tenant_id);
+
+ALTER TABLE integration_channels ENABLE ROW LEVEL SECURITY;
+ALTER TABLE integration_channels FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON integration_channels
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER update_integration_channels_updated_at BEFORE UPDATE ON integration_channels FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Tell running servers to reload a channel as soon as it changes
+CREATE OR REPLACE FUNCTION notify_integration_channel_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('integration_channels', COALESCE(NEW.id, OLD.id)::text);
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER notify_integration_channels_change AFTER INSERT OR UPDATE OR DELETE ON integration_channels
+    FOR EACH ROW EXECUTE FUNCTION notify_integration_channel_change();
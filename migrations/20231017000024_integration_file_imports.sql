@@ -0,0 +1,34 @@
+-- Files picked up from SFTP and local drop directories
+-- Migration: 20231017000024_integration_file_imports.sql
+
+-- One row per file processed, with what became of it
+CREATE TABLE integration_file_imports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    channel_id UUID NOT NULL REFERENCES integration_channels(id) ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    -- hl7, csv or ccd; NULL when the format was not recognized
+    format VARCHAR(10),
+    -- SHA-256 of the contents, so a file dropped twice is imported once
+    checksum CHAR(64) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    records_imported INTEGER NOT NULL DEFAULT 0,
+    records_failed INTEGER NOT NULL DEFAULT 0,
+    errors JSONB NOT NULL DEFAULT '[]',
+    archived_to TEXT,
+    processed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_file_format CHECK (format IN ('hl7', 'csv', 'ccd')),
+    CONSTRAINT valid_file_import_status CHECK (status IN ('imported', 'partial', 'failed', 'duplicate'))
+);
+
+CREATE INDEX idx_integration_file_imports_channel ON integration_file_imports (channel_id, processed_at DESC);
+CREATE INDEX idx_integration_file_imports_checksum ON integration_file_imports (channel_id, checksum);
+CREATE INDEX idx_integration_file_imports_tenant ON integration_file_imports (tenant_id);
+
+ALTER TABLE integration_file_imports ENABLE ROW LEVEL SECURITY;
+ALTER TABLE integration_file_imports FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON integration_file_imports
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,13 @@
+-- GraphQL persisted query allowlist
+-- Migration: 20231017000026_graphql_persisted_queries.sql
+
+-- Queries clients restricted to persisted queries, such as the patient
+-- portal, may run, by the SHA-256 of their text. The portal ships the same
+-- queries to every tenant, so the allowlist is not tenant-scoped.
+CREATE TABLE graphql_persisted_queries (
+    hash CHAR(64) PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    query TEXT NOT NULL,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,32 @@
+-- Idempotency keys for retried POST, PUT and PATCH requests
+-- Migration: 20231017000027_idempotency_keys.sql
+
+-- A client's Idempotency-Key with the response the first request using it
+-- got. The response columns are NULL while that request is still running.
+CREATE TABLE idempotency_keys (
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    -- Who sent the key, as user:<id>, key:<API key prefix> or ip:<address>;
+    -- clients choose keys, so they are only unique per client
+    scope VARCHAR(100) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    method VARCHAR(10) NOT NULL,
+    path TEXT NOT NULL,
+    -- SHA-256 of method, path, query and body; a key sent again with a
+    -- different request is rejected
+    request_hash CHAR(64) NOT NULL,
+    response_status INTEGER,
+    response_headers JSONB NOT NULL DEFAULT '{}',
+    response_body BYTEA,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    PRIMARY KEY (tenant_id, scope, idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_keys_expires ON idempotency_keys (expires_at);
+
+ALTER TABLE idempotency_keys ENABLE ROW LEVEL SECURITY;
+ALTER TABLE idempotency_keys FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON idempotency_keys
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,10 @@
+-- Full-text search over clinical document content
+-- Migration: 20231017000028_medical_record_fulltext.sql
+
+-- Stemmed English lexemes of a record's content, kept current by Postgres
+-- on every insert and update
+ALTER TABLE medical_records
+ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('english', coalesce(content, ''))) STORED;
+
+CREATE INDEX idx_medical_records_search_vector ON medical_records USING GIN(search_vector);
@@ -0,0 +1,34 @@
+-- Binary documents attached to medical records
+-- Migration: 20231017000029_medical_record_attachments.sql
+
+-- An attached file: a PDF, image or scanned form. The bytes live in the
+-- attachment store under storage_key; this row describes and verifies them.
+CREATE TABLE medical_record_attachments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    medical_record_id UUID NOT NULL REFERENCES medical_records(id),
+    title TEXT,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    -- Hex SHA-256 of the bytes, checked on every download
+    sha256 CHAR(64) NOT NULL,
+    -- Base64 SHA-1 of the bytes, as FHIR Attachment.hash carries it
+    sha1 VARCHAR(28) NOT NULL,
+    storage_key TEXT NOT NULL,
+    scan_status VARCHAR(20) NOT NULL,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+
+    CONSTRAINT valid_attachment_scan_status CHECK (scan_status IN ('clean', 'not-scanned')),
+    CONSTRAINT valid_attachment_size CHECK (size_bytes >= 0)
+);
+
+CREATE INDEX idx_medical_record_attachments_record ON medical_record_attachments (medical_record_id)
+    WHERE deleted_at IS NULL;
+
+ALTER TABLE medical_record_attachments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE medical_record_attachments FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON medical_record_attachments
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,56 @@
+-- Amendment of finalized medical records by addenda
+-- Migration: 20231017000030_medical_record_amendments.sql
+
+-- An addendum to a finalized record. The signed content is never rewritten:
+-- an addendum adds text and may strike a passage of the original or of an
+-- earlier addendum, which stays readable as struck text.
+CREATE TABLE medical_record_amendments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    medical_record_id UUID NOT NULL REFERENCES medical_records(id),
+    -- 1 for the first addendum to a record, counting up
+    sequence INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    text TEXT NOT NULL,
+    -- Passage this addendum supersedes, verbatim
+    struck_text TEXT,
+    -- Addendum whose text holds struck_text; NULL for the original content
+    strikes_amendment_id UUID REFERENCES medical_record_amendments(id),
+    -- Record version the addendum produced
+    version_id VARCHAR(20) NOT NULL,
+    amended_by UUID,
+    amended_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_amendment_sequence UNIQUE (medical_record_id, sequence),
+    CONSTRAINT valid_amendment_reason CHECK (length(trim(reason)) > 0),
+    CONSTRAINT valid_amendment_strike CHECK (strikes_amendment_id IS NULL OR struck_text IS NOT NULL)
+);
+
+ALTER TABLE medical_record_amendments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE medical_record_amendments FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON medical_record_amendments
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- Addenda are part of the legal record and are never changed or removed
+CREATE OR REPLACE FUNCTION reject_amendment_change() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'medical record amendments cannot be changed or deleted';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER medical_record_amendments_immutable BEFORE UPDATE OR DELETE ON medical_record_amendments
+    FOR EACH ROW EXECUTE FUNCTION reject_amendment_change();
+
+-- Once finalized, a record's content is fixed; changes go through addenda
+CREATE OR REPLACE FUNCTION reject_final_content_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.status IN ('final', 'amended') AND NEW.content IS DISTINCT FROM OLD.content THEN
+        RAISE EXCEPTION 'medical record % is final; amend it with an addendum', OLD.id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER medical_records_final_content BEFORE UPDATE ON medical_records
+    FOR EACH ROW EXECUTE FUNCTION reject_final_content_change();
@@ -0,0 +1,43 @@
+-- Clinical note templates and structured data on medical records
+-- Migration: 20231017000031_note_templates.sql
+
+-- A tenant's own note templates; SOAP and discharge summary templates are
+-- built in. fields holds the template's structured fields, each with its
+-- type, section, whether it is required and the FHIR element it binds to.
+CREATE TABLE note_templates (
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    id VARCHAR(64) NOT NULL,
+    title VARCHAR(200) NOT NULL,
+    record_type VARCHAR(30) NOT NULL,
+    fields JSONB NOT NULL,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+
+    PRIMARY KEY (tenant_id, id),
+    CONSTRAINT valid_note_template_id CHECK (id ~ '^[a-z0-9][a-z0-9-]*$'),
+    CONSTRAINT valid_note_template_record_type CHECK (record_type IN ('progress-note', 'discharge-summary', 'operative-note', 'consultation', 'diagnostic-report'))
+);
+
+ALTER TABLE note_templates ENABLE ROW LEVEL SECURITY;
+ALTER TABLE note_templates FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON note_templates
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- The template a record was written with, and the values of its fields
+ALTER TABLE medical_records
+ADD COLUMN template_id VARCHAR(64),
+ADD COLUMN structured_data JSONB;
+
+-- Structured data is as fixed as the content once a record is finalized
+CREATE OR REPLACE FUNCTION reject_final_content_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.status IN ('final', 'amended')
+        AND (NEW.content IS DISTINCT FROM OLD.content OR NEW.structured_data IS DISTINCT FROM OLD.structured_data) THEN
+        RAISE EXCEPTION 'medical record % is final; amend it with an addendum', OLD.id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,56 @@
+-- Conditions and the per-patient problem list
+-- Migration: 20231017000032_conditions.sql
+
+-- A problem, diagnosis or health concern. Problem-list items make up the
+-- patient's problem list; encounter diagnoses belong to one visit.
+CREATE TABLE conditions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID,
+    clinical_status VARCHAR(20) NOT NULL,
+    verification_status VARCHAR(20) NOT NULL,
+    category VARCHAR(30) NOT NULL,
+    code JSONB NOT NULL, -- FHIR CodeableConcept
+    -- The SNOMED CT concept in code, for lookups by concept
+    snomed_code VARCHAR(18) NOT NULL,
+    severity JSONB, -- FHIR CodeableConcept
+    onset_date_time TIMESTAMP WITH TIME ZONE,
+    abatement_date_time TIMESTAMP WITH TIME ZONE,
+    recorded_date TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    recorder JSONB, -- FHIR Reference
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    meta JSONB NOT NULL, -- FHIR ResourceMeta
+
+    CONSTRAINT valid_condition_clinical_status CHECK (clinical_status IN ('active', 'recurrence', 'relapse', 'inactive', 'remission', 'resolved')),
+    CONSTRAINT valid_condition_verification_status CHECK (verification_status IN ('unconfirmed', 'provisional', 'differential', 'confirmed', 'refuted', 'entered-in-error')),
+    CONSTRAINT valid_condition_category CHECK (category IN ('problem-list-item', 'encounter-diagnosis')),
+    -- FHIR con-4: an abated condition is inactive, in remission or resolved
+    CONSTRAINT valid_condition_abatement CHECK (
+        abatement_date_time IS NULL OR clinical_status IN ('inactive', 'remission', 'resolved')
+    ),
+    CONSTRAINT valid_condition_period CHECK (
+        onset_date_time IS NULL OR abatement_date_time IS NULL OR abatement_date_time >= onset_date_time
+    )
+);
+
+CREATE INDEX idx_conditions_patient ON conditions (patient_id, category, clinical_status) WHERE deleted_at IS NULL;
+CREATE INDEX idx_conditions_encounter ON conditions (encounter_id) WHERE deleted_at IS NULL;
+CREATE INDEX idx_conditions_snomed ON conditions (snomed_code) WHERE deleted_at IS NULL;
+
+CREATE TRIGGER update_conditions_updated_at BEFORE UPDATE ON conditions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE conditions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE conditions FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON conditions
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER conditions_history_insert AFTER INSERT ON conditions
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('Condition');
+CREATE TRIGGER conditions_history_update AFTER UPDATE ON conditions
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('Condition');
@@ -0,0 +1,53 @@
+-- Vital signs, served as Observations of the FHIR vital-signs profiles
+-- Migration: 20231017000033_vital_signs.sql
+
+CREATE TABLE vital_signs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID,
+    status VARCHAR(20) NOT NULL,
+    kind VARCHAR(30) NOT NULL,
+    -- LOINC code of the kind, for lookups by code
+    loinc_code VARCHAR(10) NOT NULL,
+    -- The measurement as entered, with its UCUM unit; NULL for blood
+    -- pressure, which is measured by its components
+    value DOUBLE PRECISION,
+    unit VARCHAR(20),
+    component JSONB NOT NULL DEFAULT '[]', -- [{kind, value: FHIR Quantity}]
+    effective_date_time TIMESTAMP WITH TIME ZONE NOT NULL,
+    issued TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    device JSONB, -- reference, manufacturer, model, serial_number, udi
+    performer JSONB, -- FHIR Reference
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    meta JSONB NOT NULL, -- FHIR ResourceMeta
+
+    CONSTRAINT valid_vital_sign_status CHECK (status IN ('registered', 'preliminary', 'final', 'amended', 'corrected', 'cancelled', 'entered-in-error')),
+    CONSTRAINT valid_vital_sign_kind CHECK (kind IN (
+        'respiratory-rate', 'heart-rate', 'oxygen-saturation', 'body-temperature', 'body-height',
+        'head-circumference', 'body-weight', 'bmi', 'blood-pressure'
+    )),
+    CONSTRAINT valid_vital_sign_value CHECK ((value IS NULL) = (unit IS NULL)),
+    CONSTRAINT valid_vital_sign_measurement CHECK (value IS NOT NULL OR jsonb_array_length(component) > 0)
+);
+
+-- Trends read one patient's signs of some kinds over a time range
+CREATE INDEX idx_vital_signs_patient ON vital_signs (patient_id, kind, effective_date_time) WHERE deleted_at IS NULL;
+CREATE INDEX idx_vital_signs_encounter ON vital_signs (encounter_id) WHERE deleted_at IS NULL;
+
+CREATE TRIGGER update_vital_signs_updated_at BEFORE UPDATE ON vital_signs FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE vital_signs ENABLE ROW LEVEL SECURITY;
+ALTER TABLE vital_signs FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON vital_signs
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER vital_signs_history_insert AFTER INSERT ON vital_signs
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('Observation');
+CREATE TRIGGER vital_signs_history_update AFTER UPDATE ON vital_signs
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('Observation');
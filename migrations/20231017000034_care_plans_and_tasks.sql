@@ -0,0 +1,103 @@
+-- Care plans, their scheduled activities, and the tasks that carry them out
+-- Migration: 20231017000034_care_plans_and_tasks.sql
+
+-- How a patient's care is to be delivered. Activities are kept with the
+-- plan; each has a stable id that the tasks scheduled from it refer to.
+CREATE TABLE care_plans (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    status VARCHAR(20) NOT NULL,
+    intent VARCHAR(20) NOT NULL,
+    title VARCHAR(255),
+    description TEXT,
+    category JSONB NOT NULL DEFAULT '[]', -- [FHIR CodeableConcept]
+    period_start TIMESTAMP WITH TIME ZONE,
+    period_end TIMESTAMP WITH TIME ZONE,
+    author_id UUID,
+    -- Conditions the plan addresses
+    addresses UUID[] NOT NULL DEFAULT '{}',
+    activities JSONB NOT NULL DEFAULT '[]', -- [{id, code, description, status, performer_id, schedule}]
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    meta JSONB NOT NULL, -- FHIR ResourceMeta
+
+    CONSTRAINT valid_care_plan_status CHECK (status IN ('draft', 'active', 'on-hold', 'revoked', 'completed', 'entered-in-error')),
+    CONSTRAINT valid_care_plan_intent CHECK (intent IN ('proposal', 'plan', 'order', 'option')),
+    CONSTRAINT valid_care_plan_period CHECK (
+        period_start IS NULL OR period_end IS NULL OR period_end >= period_start
+    )
+);
+
+CREATE INDEX idx_care_plans_patient ON care_plans (patient_id, status) WHERE deleted_at IS NULL;
+CREATE INDEX idx_care_plans_addresses ON care_plans USING GIN (addresses) WHERE deleted_at IS NULL;
+
+-- Work assigned to a care-team member, scheduled from a care plan activity
+-- or created on its own
+CREATE TABLE tasks (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    care_plan_id UUID REFERENCES care_plans(id),
+    activity_id UUID,
+    status VARCHAR(20) NOT NULL,
+    status_reason TEXT,
+    priority VARCHAR(10) NOT NULL DEFAULT 'routine',
+    code JSONB, -- FHIR CodeableConcept
+    description TEXT,
+    owner_id UUID,
+    requester_id UUID,
+    due TIMESTAMP WITH TIME ZONE,
+    authored_on TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_modified TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    meta JSONB NOT NULL, -- FHIR ResourceMeta
+
+    CONSTRAINT valid_task_status CHECK (status IN (
+        'draft', 'requested', 'received', 'accepted', 'rejected', 'ready', 'cancelled',
+        'in-progress', 'on-hold', 'failed', 'completed', 'entered-in-error'
+    )),
+    CONSTRAINT valid_task_priority CHECK (priority IN ('routine', 'urgent', 'asap', 'stat')),
+    CONSTRAINT valid_task_activity CHECK (activity_id IS NULL OR care_plan_id IS NOT NULL)
+);
+
+-- One task per occurrence of an activity, so scheduling a plan again does
+-- not duplicate its tasks; deleted occurrences are not recreated
+CREATE UNIQUE INDEX idx_tasks_occurrence ON tasks (care_plan_id, activity_id, due);
+CREATE INDEX idx_tasks_patient ON tasks (patient_id, status) WHERE deleted_at IS NULL;
+CREATE INDEX idx_tasks_owner ON tasks (owner_id, status) WHERE deleted_at IS NULL;
+-- Open tasks by due date, for overdue queries
+CREATE INDEX idx_tasks_open_due ON tasks (due) WHERE deleted_at IS NULL
+    AND status NOT IN ('rejected', 'cancelled', 'failed', 'completed', 'entered-in-error');
+
+CREATE TRIGGER update_care_plans_updated_at BEFORE UPDATE ON care_plans FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_tasks_updated_at BEFORE UPDATE ON tasks FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE care_plans ENABLE ROW LEVEL SECURITY;
+ALTER TABLE care_plans FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON care_plans
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE tasks ENABLE ROW LEVEL SECURITY;
+ALTER TABLE tasks FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON tasks
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER care_plans_history_insert AFTER INSERT ON care_plans
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('CarePlan');
+CREATE TRIGGER care_plans_history_update AFTER UPDATE ON care_plans
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('CarePlan');
+
+CREATE TRIGGER tasks_history_insert AFTER INSERT ON tasks
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('Task');
+CREATE TRIGGER tasks_history_update AFTER UPDATE ON tasks
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('Task');
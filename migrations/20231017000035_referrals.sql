@@ -0,0 +1,60 @@
+-- Referrals made here and received from other organizations
+-- Migration: 20231017000035_referrals.sql
+
+-- A referral of a patient, served as a FHIR ServiceRequest. Acceptance and
+-- the appointment booked for it are tracked alongside the request status.
+CREATE TABLE referrals (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    direction VARCHAR(10) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    priority VARCHAR(10) NOT NULL DEFAULT 'routine',
+    -- The sender's number for an inbound referral
+    identifier VARCHAR(100),
+    code JSONB, -- FHIR CodeableConcept
+    specialty JSONB, -- FHIR CodeableConcept
+    reason_code JSONB NOT NULL DEFAULT '[]', -- [FHIR CodeableConcept]
+    reason_condition_ids UUID[] NOT NULL DEFAULT '{}',
+    requester_id UUID,
+    requester_display VARCHAR(255),
+    performer_id UUID,
+    performer_display VARCHAR(255),
+    acceptance VARCHAR(10) NOT NULL DEFAULT 'pending',
+    responded_at TIMESTAMP WITH TIME ZONE,
+    response_note TEXT,
+    appointment_id UUID REFERENCES appointments(id),
+    authored_on TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    meta JSONB NOT NULL, -- FHIR ResourceMeta
+
+    CONSTRAINT valid_referral_direction CHECK (direction IN ('outbound', 'inbound')),
+    CONSTRAINT valid_referral_status CHECK (status IN ('draft', 'active', 'on-hold', 'revoked', 'completed', 'entered-in-error')),
+    CONSTRAINT valid_referral_priority CHECK (priority IN ('routine', 'urgent', 'asap', 'stat')),
+    CONSTRAINT valid_referral_acceptance CHECK (acceptance IN ('pending', 'accepted', 'declined')),
+    CONSTRAINT valid_referral_appointment CHECK (appointment_id IS NULL OR acceptance = 'accepted')
+);
+
+CREATE INDEX idx_referrals_patient ON referrals (patient_id, status) WHERE deleted_at IS NULL;
+CREATE INDEX idx_referrals_worklist ON referrals (direction, acceptance, status) WHERE deleted_at IS NULL;
+CREATE INDEX idx_referrals_appointment ON referrals (appointment_id) WHERE appointment_id IS NOT NULL;
+-- A sender's referral is received once, however often it is resent
+CREATE UNIQUE INDEX idx_referrals_inbound ON referrals (requester_display, identifier)
+    WHERE direction = 'inbound' AND identifier IS NOT NULL AND deleted_at IS NULL;
+
+CREATE TRIGGER update_referrals_updated_at BEFORE UPDATE ON referrals FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE referrals ENABLE ROW LEVEL SECURITY;
+ALTER TABLE referrals FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON referrals
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+CREATE TRIGGER referrals_history_insert AFTER INSERT ON referrals
+    FOR EACH ROW EXECUTE FUNCTION record_resource_history('ServiceRequest');
+CREATE TRIGGER referrals_history_update AFTER UPDATE ON referrals
+    FOR EACH ROW WHEN (OLD.meta->>'version_id' IS DISTINCT FROM NEW.meta->>'version_id')
+    EXECUTE FUNCTION record_resource_history('ServiceRequest');
@@ -0,0 +1,117 @@
+-- Inventory of consumables and equipment
+-- Migration: 20231017000036_inventory.sql
+
+-- The item master. Stock is not kept here; it is the sum of an item's
+-- stock transactions, per lot.
+CREATE TABLE inventory_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    sku VARCHAR(64) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    category VARCHAR(20) NOT NULL,
+    -- Unit stock is counted in, e.g. box or each
+    unit VARCHAR(30) NOT NULL,
+    -- Stock at or below which the item is reordered
+    reorder_level INTEGER NOT NULL DEFAULT 0,
+    -- Whether receipts must give a lot number and expiry date
+    tracks_expiry BOOLEAN NOT NULL DEFAULT false,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+
+    CONSTRAINT valid_inventory_item_category CHECK (category IN ('consumable', 'equipment')),
+    CONSTRAINT valid_inventory_item_reorder_level CHECK (reorder_level >= 0)
+);
+
+CREATE UNIQUE INDEX idx_inventory_items_sku ON inventory_items (tenant_id, sku) WHERE deleted_at IS NULL;
+
+-- Orders to suppliers. lines holds what is ordered and how much of it has
+-- been received: [{id, item_id, quantity_ordered, quantity_received, unit_price}]
+CREATE TABLE purchase_orders (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    order_number VARCHAR(64) NOT NULL,
+    supplier VARCHAR(255) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'draft',
+    lines JSONB NOT NULL DEFAULT '[]',
+    expected_on DATE,
+    note TEXT,
+    created_by UUID,
+    ordered_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_purchase_order_status CHECK (status IN ('draft', 'ordered', 'partially-received', 'received', 'cancelled'))
+);
+
+CREATE UNIQUE INDEX idx_purchase_orders_number ON purchase_orders (tenant_id, order_number);
+CREATE INDEX idx_purchase_orders_status ON purchase_orders (status, created_at);
+
+-- Counts of stock on hand; each count that differs from the books is
+-- recorded as an adjustment transaction
+CREATE TABLE stock_takes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    counted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    counted_by UUID,
+    note TEXT
+);
+
+-- The stock ledger. Receipts add stock, issues to departments take it away,
+-- and adjustments correct it; quantity is signed accordingly. Rows are
+-- never changed once written.
+CREATE TABLE stock_transactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    item_id UUID NOT NULL REFERENCES inventory_items(id),
+    kind VARCHAR(20) NOT NULL,
+    quantity INTEGER NOT NULL,
+    lot_number VARCHAR(64),
+    expiry_date DATE,
+    -- Department stock was issued to
+    department VARCHAR(100),
+    purchase_order_id UUID REFERENCES purchase_orders(id),
+    stock_take_id UUID REFERENCES stock_takes(id),
+    reason TEXT,
+    recorded_by UUID,
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_stock_transaction_kind CHECK (kind IN ('receipt', 'issue', 'adjustment')),
+    CONSTRAINT valid_stock_transaction_quantity CHECK (
+        (kind = 'receipt' AND quantity > 0)
+        OR (kind = 'issue' AND quantity < 0 AND department IS NOT NULL)
+        OR (kind = 'adjustment' AND quantity <> 0)
+    )
+);
+
+CREATE INDEX idx_stock_transactions_item ON stock_transactions (item_id, lot_number);
+CREATE INDEX idx_stock_transactions_expiry ON stock_transactions (expiry_date) WHERE expiry_date IS NOT NULL;
+CREATE INDEX idx_stock_transactions_issues ON stock_transactions (occurred_at, department) WHERE kind = 'issue';
+
+CREATE TRIGGER update_inventory_items_updated_at BEFORE UPDATE ON inventory_items FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_purchase_orders_updated_at BEFORE UPDATE ON purchase_orders FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE inventory_items ENABLE ROW LEVEL SECURITY;
+ALTER TABLE inventory_items FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON inventory_items
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE purchase_orders ENABLE ROW LEVEL SECURITY;
+ALTER TABLE purchase_orders FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON purchase_orders
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE stock_takes ENABLE ROW LEVEL SECURITY;
+ALTER TABLE stock_takes FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON stock_takes
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE stock_transactions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE stock_transactions FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON stock_transactions
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,83 @@
+-- Operating theatres, their schedule and surgical safety checklists
+-- Migration: 20231017000037_operating_theatres.sql
+
+-- A theatre and the equipment installed in it
+CREATE TABLE theatres (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    name VARCHAR(100) NOT NULL,
+    equipment TEXT[] NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_theatres_name ON theatres (tenant_id, name);
+
+-- Equipment moved between theatres, e.g. C-arms, and how many units there are
+CREATE TABLE theatre_equipment (
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    code VARCHAR(64) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    units INTEGER NOT NULL,
+
+    PRIMARY KEY (tenant_id, code),
+    CONSTRAINT valid_theatre_equipment_units CHECK (units >= 0)
+);
+
+-- An operation booked in a theatre. team holds [{user_id, role}] and
+-- checklist the WHO surgical safety checklist phases recorded so far.
+CREATE TABLE surgical_cases (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID REFERENCES encounters(id),
+    theatre_id UUID NOT NULL REFERENCES theatres(id),
+    procedure JSONB NOT NULL, -- FHIR CodeableConcept
+    -- Site and side of the operation, as marked
+    site VARCHAR(255),
+    status VARCHAR(20) NOT NULL DEFAULT 'scheduled',
+    scheduled_start TIMESTAMP WITH TIME ZONE NOT NULL,
+    scheduled_end TIMESTAMP WITH TIME ZONE NOT NULL,
+    team JSONB NOT NULL DEFAULT '[]',
+    equipment TEXT[] NOT NULL DEFAULT '{}',
+    checklist JSONB NOT NULL DEFAULT '{}',
+    actual_start TIMESTAMP WITH TIME ZONE,
+    actual_end TIMESTAMP WITH TIME ZONE,
+    cancellation_reason TEXT,
+    operative_note_id UUID REFERENCES medical_records(id),
+    note TEXT,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_surgical_case_status CHECK (status IN ('scheduled', 'in-progress', 'completed', 'cancelled')),
+    CONSTRAINT valid_surgical_case_schedule CHECK (scheduled_end > scheduled_start)
+);
+
+-- Booked cases by time, for the theatre list and clash checks
+CREATE INDEX idx_surgical_cases_schedule ON surgical_cases (scheduled_start, scheduled_end)
+    WHERE status IN ('scheduled', 'in-progress');
+CREATE INDEX idx_surgical_cases_theatre ON surgical_cases (theatre_id, scheduled_start);
+CREATE INDEX idx_surgical_cases_patient ON surgical_cases (patient_id);
+
+CREATE TRIGGER update_theatres_updated_at BEFORE UPDATE ON theatres FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_surgical_cases_updated_at BEFORE UPDATE ON surgical_cases FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE theatres ENABLE ROW LEVEL SECURITY;
+ALTER TABLE theatres FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON theatres
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE theatre_equipment ENABLE ROW LEVEL SECURITY;
+ALTER TABLE theatre_equipment FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON theatre_equipment
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE surgical_cases ENABLE ROW LEVEL SECURITY;
+ALTER TABLE surgical_cases FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON surgical_cases
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,52 @@
+-- Emergency department visits, triage and the tracking board
+-- Migration: 20231017000038_emergency_department.sql
+
+-- A patient's stay in the emergency department, from arrival to departure.
+-- triage holds the latest triage: scale, level, reasons and the assessment
+-- it was made from; triage_level and urgency repeat it for the tracking
+-- board and for authorization, which raises the urgency of requests about
+-- the patient while the visit is open.
+CREATE TABLE ed_visits (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID REFERENCES encounters(id),
+    chief_complaint TEXT NOT NULL,
+    arrival_mode VARCHAR(50),
+    arrived_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    status VARCHAR(20) NOT NULL DEFAULT 'waiting',
+    triage JSONB,
+    triage_level SMALLINT,
+    urgency VARCHAR(20),
+    -- Bed, bay or area on the board
+    location VARCHAR(100),
+    -- When and by whom the patient was first seen by a doctor
+    first_seen_at TIMESTAMP WITH TIME ZONE,
+    seen_by UUID,
+    departed_at TIMESTAMP WITH TIME ZONE,
+    disposition VARCHAR(30),
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_ed_visit_status CHECK (status IN ('waiting', 'in-treatment', 'awaiting-results', 'departed')),
+    CONSTRAINT valid_ed_triage_level CHECK (triage_level BETWEEN 1 AND 5),
+    CONSTRAINT valid_ed_urgency CHECK (urgency IN ('routine', 'urgent', 'emergency', 'critical')),
+    CONSTRAINT valid_ed_disposition CHECK (disposition IN (
+        'admitted', 'discharged', 'transferred', 'left-without-being-seen', 'deceased'
+    )),
+    CONSTRAINT ed_departure_has_disposition CHECK ((status = 'departed') = (departed_at IS NOT NULL AND disposition IS NOT NULL))
+);
+
+-- A patient is in the department once at a time
+CREATE UNIQUE INDEX idx_ed_visits_open_patient ON ed_visits (tenant_id, patient_id) WHERE status <> 'departed';
+CREATE INDEX idx_ed_visits_board ON ed_visits (status, triage_level, arrived_at) WHERE status <> 'departed';
+CREATE INDEX idx_ed_visits_arrived ON ed_visits (arrived_at);
+
+CREATE TRIGGER update_ed_visits_updated_at BEFORE UPDATE ON ed_visits FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE ed_visits ENABLE ROW LEVEL SECURITY;
+ALTER TABLE ed_visits FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON ed_visits
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
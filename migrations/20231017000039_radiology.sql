@@ -0,0 +1,60 @@
+-- Radiology orders, the modality worklist, DICOM studies and reports
+-- Migration: 20231017000039_radiology.sql
+
+-- Accession numbers, which modalities copy from the worklist into the
+-- studies they acquire
+CREATE SEQUENCE imaging_accession_seq;
+
+-- An imaging order, served as a FHIR ServiceRequest. The study instance UID
+-- is assigned on ordering and sent in the worklist; study holds the DICOM
+-- study linked to the order once it is performed, and report_id the
+-- diagnostic report medical record written on it.
+CREATE TABLE imaging_orders (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID REFERENCES encounters(id),
+    status VARCHAR(20) NOT NULL DEFAULT 'ordered',
+    priority VARCHAR(10) NOT NULL DEFAULT 'routine',
+    code JSONB NOT NULL, -- FHIR CodeableConcept
+    -- DICOM modality, e.g. CT, MR, CR, US
+    modality VARCHAR(16) NOT NULL,
+    body_site VARCHAR(255),
+    reason TEXT,
+    requester_id UUID,
+    requester_display VARCHAR(255),
+    accession_number VARCHAR(16) NOT NULL DEFAULT 'A' || lpad(nextval('imaging_accession_seq')::text, 9, '0'),
+    study_instance_uid VARCHAR(64) NOT NULL,
+    scheduled_at TIMESTAMP WITH TIME ZONE,
+    -- AE title of the modality the order is scheduled on
+    station_ae_title VARCHAR(16),
+    study JSONB,
+    performed_at TIMESTAMP WITH TIME ZONE,
+    report_id UUID REFERENCES medical_records(id),
+    reported_at TIMESTAMP WITH TIME ZONE,
+    reported_by UUID,
+    cancellation_reason TEXT,
+    note TEXT,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_imaging_order_status CHECK (status IN ('ordered', 'scheduled', 'performed', 'reported', 'cancelled')),
+    CONSTRAINT valid_imaging_order_priority CHECK (priority IN ('routine', 'urgent', 'asap', 'stat')),
+    CONSTRAINT imaging_order_scheduled CHECK (status <> 'scheduled' OR scheduled_at IS NOT NULL),
+    CONSTRAINT imaging_order_performed CHECK (status NOT IN ('performed', 'reported') OR study IS NOT NULL),
+    CONSTRAINT imaging_order_reported CHECK ((status = 'reported') = (reported_at IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX idx_imaging_orders_accession ON imaging_orders (accession_number);
+CREATE UNIQUE INDEX idx_imaging_orders_study ON imaging_orders (study_instance_uid);
+CREATE INDEX idx_imaging_orders_patient ON imaging_orders (patient_id, status);
+CREATE INDEX idx_imaging_orders_worklist ON imaging_orders (modality, scheduled_at) WHERE status = 'scheduled';
+
+CREATE TRIGGER update_imaging_orders_updated_at BEFORE UPDATE ON imaging_orders FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE imaging_orders ENABLE ROW LEVEL SECURITY;
+ALTER TABLE imaging_orders FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON imaging_orders
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,111 @@
+-- Birth and death notifications for civil registration
+-- Migration: 20231017000040_vital_records.sql
+
+-- A birth or death notification. patient_id is the mother of a birth or
+-- the deceased; details holds the notification's particulars as the service
+-- structures them. Once finalized, a record is only changed by amendment.
+CREATE TABLE vital_records (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    kind VARCHAR(10) NOT NULL,
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID REFERENCES encounters(id),
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'draft',
+    -- 1 until the record is first amended, counting up
+    version INTEGER NOT NULL DEFAULT 1,
+    details JSONB NOT NULL,
+    -- ICD-10 underlying cause of a death
+    underlying_cause_code VARCHAR(10),
+    -- Number the civil registry registered the event under
+    registration_number VARCHAR(64),
+    registered_at TIMESTAMP WITH TIME ZONE,
+    finalized_at TIMESTAMP WITH TIME ZONE,
+    finalized_by UUID,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_vital_record_kind CHECK (kind IN ('birth', 'death')),
+    CONSTRAINT valid_vital_record_status CHECK (status IN ('draft', 'final', 'amended')),
+    CONSTRAINT vital_record_finalized CHECK ((status = 'draft') = (finalized_at IS NULL)),
+    CONSTRAINT vital_record_registered CHECK ((registration_number IS NULL) = (registered_at IS NULL)),
+    CONSTRAINT vital_record_registered_final CHECK (registration_number IS NULL OR status <> 'draft')
+);
+
+CREATE INDEX idx_vital_records_period ON vital_records (kind, occurred_at);
+CREATE INDEX idx_vital_records_patient ON vital_records (patient_id);
+CREATE UNIQUE INDEX idx_vital_records_registration ON vital_records (tenant_id, kind, registration_number)
+    WHERE registration_number IS NOT NULL;
+
+CREATE TRIGGER update_vital_records_updated_at BEFORE UPDATE ON vital_records FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE vital_records ENABLE ROW LEVEL SECURITY;
+ALTER TABLE vital_records FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON vital_records
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- An amendment of a finalized record: why it was made and the record's
+-- particulars as they stood before it
+CREATE TABLE vital_record_amendments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    vital_record_id UUID NOT NULL REFERENCES vital_records(id),
+    -- Record version the amendment produced
+    version INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    previous JSONB NOT NULL,
+    amended_by UUID,
+    amended_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_vital_record_amendment UNIQUE (vital_record_id, version),
+    CONSTRAINT valid_vital_record_amendment_reason CHECK (length(trim(reason)) > 0)
+);
+
+ALTER TABLE vital_record_amendments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE vital_record_amendments FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON vital_record_amendments
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- Amendments are part of the legal record and are never changed or removed
+CREATE OR REPLACE FUNCTION reject_vital_record_amendment_change() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'vital record amendments cannot be changed or deleted';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER vital_record_amendments_immutable BEFORE UPDATE OR DELETE ON vital_record_amendments
+    FOR EACH ROW EXECUTE FUNCTION reject_vital_record_amendment_change();
+
+-- A finalized record is never deleted or returned to draft, and its
+-- particulars only change together with an amendment recording why
+CREATE OR REPLACE FUNCTION guard_final_vital_record() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        IF OLD.status <> 'draft' THEN
+            RAISE EXCEPTION 'vital record % is final and cannot be deleted', OLD.id;
+        END IF;
+        RETURN OLD;
+    END IF;
+    IF OLD.status = 'draft' THEN
+        RETURN NEW;
+    END IF;
+    IF NEW.status = 'draft' OR NEW.kind <> OLD.kind THEN
+        RAISE EXCEPTION 'vital record % is final', OLD.id;
+    END IF;
+    IF (NEW.patient_id, NEW.encounter_id, NEW.occurred_at, NEW.details, NEW.version)
+        IS DISTINCT FROM (OLD.patient_id, OLD.encounter_id, OLD.occurred_at, OLD.details, OLD.version)
+        AND NOT EXISTS (
+            SELECT 1 FROM vital_record_amendments
+            WHERE vital_record_id = OLD.id AND version = NEW.version AND NEW.version = OLD.version + 1
+        ) THEN
+        RAISE EXCEPTION 'vital record % is final; change it by amendment', OLD.id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER vital_records_final BEFORE UPDATE OR DELETE ON vital_records
+    FOR EACH ROW EXECUTE FUNCTION guard_final_vital_record();
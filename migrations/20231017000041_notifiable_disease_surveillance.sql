@@ -0,0 +1,81 @@
+-- Notifiable disease surveillance: code lists and case reports
+-- Migration: 20231017000041_notifiable_disease_surveillance.sql
+
+-- A disease to be notified to public health, recognized in diagnoses by its
+-- ICD-10 codes (matched as prefixes, A00 covering A00.9) or SNOMED CT
+-- concepts, and reported in report_format within deadline_hours of being
+-- diagnosed
+CREATE TABLE notifiable_diseases (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    code VARCHAR(64) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    icd10_codes TEXT[] NOT NULL DEFAULT '{}',
+    snomed_codes TEXT[] NOT NULL DEFAULT '{}',
+    report_format VARCHAR(10) NOT NULL DEFAULT 'fhir',
+    deadline_hours INTEGER NOT NULL DEFAULT 24,
+    -- Whether suspected (unconfirmed, provisional, differential) diagnoses
+    -- are reported, or only confirmed ones
+    report_suspected BOOLEAN NOT NULL DEFAULT TRUE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_notifiable_disease_code UNIQUE (tenant_id, code),
+    CONSTRAINT valid_notifiable_disease_format CHECK (report_format IN ('fhir', 'idsp')),
+    CONSTRAINT valid_notifiable_disease_deadline CHECK (deadline_hours > 0),
+    CONSTRAINT notifiable_disease_codes CHECK (cardinality(icd10_codes) + cardinality(snomed_codes) > 0)
+);
+
+CREATE TRIGGER update_notifiable_diseases_updated_at BEFORE UPDATE ON notifiable_diseases FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE notifiable_diseases ENABLE ROW LEVEL SECURITY;
+ALTER TABLE notifiable_diseases FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON notifiable_diseases
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- A case of a notifiable disease, from the diagnosis it was detected in to
+-- the authority's answer. payload is the report as it was last submitted;
+-- response the authority's acknowledgement or rejection, or why the report
+-- was cancelled.
+CREATE TABLE case_reports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    disease_id UUID NOT NULL REFERENCES notifiable_diseases(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    condition_id UUID NOT NULL REFERENCES conditions(id),
+    encounter_id UUID,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    report_format VARCHAR(10) NOT NULL,
+    detected_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    due_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    submitted_at TIMESTAMP WITH TIME ZONE,
+    submitted_by UUID,
+    -- The authority's reference for the submission
+    submission_reference VARCHAR(255),
+    payload JSONB,
+    response TEXT,
+    responded_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_case_report UNIQUE (disease_id, condition_id),
+    CONSTRAINT valid_case_report_status CHECK (status IN ('pending', 'submitted', 'acknowledged', 'rejected', 'cancelled')),
+    CONSTRAINT valid_case_report_format CHECK (report_format IN ('fhir', 'idsp')),
+    CONSTRAINT case_report_submitted CHECK (
+        status NOT IN ('submitted', 'acknowledged', 'rejected') OR (submitted_at IS NOT NULL AND payload IS NOT NULL)
+    )
+);
+
+CREATE INDEX idx_case_reports_due ON case_reports (due_at) WHERE status IN ('pending', 'rejected');
+CREATE INDEX idx_case_reports_patient ON case_reports (patient_id);
+CREATE INDEX idx_case_reports_condition ON case_reports (condition_id);
+
+CREATE TRIGGER update_case_reports_updated_at BEFORE UPDATE ON case_reports FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE case_reports ENABLE ROW LEVEL SECURITY;
+ALTER TABLE case_reports FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON case_reports
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
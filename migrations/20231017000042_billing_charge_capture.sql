@@ -0,0 +1,77 @@
+-- Billing code systems and charge capture
+-- Migration: 20231017000042_billing_charge_capture.sql
+
+-- CPT, HCPCS Level II, NDC and modifier code sets, loaded from the AMA's,
+-- CMS's and FDA's releases. They are national code sets, the same for every
+-- tenant, so they are not tenant-scoped. A code applies to services from its
+-- effective date until its termination date.
+CREATE TABLE billing_codes (
+    system VARCHAR(10) NOT NULL,
+    code VARCHAR(11) NOT NULL,
+    display TEXT NOT NULL,
+    effective_date DATE NOT NULL,
+    termination_date DATE,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (system, code),
+    CONSTRAINT valid_billing_code_system CHECK (system IN ('cpt', 'hcpcs', 'ndc', 'modifier')),
+    CONSTRAINT valid_billing_code_dates CHECK (termination_date IS NULL OR termination_date >= effective_date)
+);
+
+-- NCCI procedure-to-procedure edits, from CMS's quarterly files. The column
+-- two code is not paid when billed with the column one code for the same
+-- patient on the same day, unless the modifier indicator is 1 and an NCCI
+-- modifier shows the services were distinct. Indicator 9 marks edits that
+-- do not apply. National, so not tenant-scoped.
+CREATE TABLE cci_edits (
+    column_one VARCHAR(5) NOT NULL,
+    column_two VARCHAR(5) NOT NULL,
+    effective_date DATE NOT NULL,
+    deletion_date DATE,
+    modifier_indicator CHAR(1) NOT NULL,
+
+    PRIMARY KEY (column_one, column_two, effective_date),
+    CONSTRAINT valid_cci_modifier_indicator CHECK (modifier_indicator IN ('0', '1', '9'))
+);
+
+CREATE INDEX idx_cci_edits_column_two ON cci_edits (column_two);
+
+-- Services captured for billing, a line each: a CPT or HCPCS code with up to
+-- four modifiers, and the NDC of the drug given for drug codes. unit_price is
+-- in the smallest unit of the currency, e.g. cents.
+CREATE TABLE charges (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID NOT NULL,
+    code_system VARCHAR(10) NOT NULL,
+    code VARCHAR(5) NOT NULL,
+    modifiers TEXT[] NOT NULL DEFAULT '{}',
+    ndc VARCHAR(11),
+    units INTEGER NOT NULL,
+    unit_price BIGINT NOT NULL,
+    service_date DATE NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'active',
+    captured_by UUID,
+    void_reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_charge_code_system CHECK (code_system IN ('cpt', 'hcpcs')),
+    CONSTRAINT valid_charge_status CHECK (status IN ('active', 'voided')),
+    CONSTRAINT valid_charge_units CHECK (units > 0),
+    CONSTRAINT valid_charge_price CHECK (unit_price >= 0),
+    CONSTRAINT valid_charge_modifiers CHECK (cardinality(modifiers) <= 4),
+    CONSTRAINT voided_charge_reason CHECK (status <> 'voided' OR void_reason IS NOT NULL)
+);
+
+CREATE INDEX idx_charges_encounter ON charges (encounter_id, service_date) WHERE status = 'active';
+CREATE INDEX idx_charges_patient ON charges (patient_id);
+
+CREATE TRIGGER update_charges_updated_at BEFORE UPDATE ON charges FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE charges ENABLE ROW LEVEL SECURITY;
+ALTER TABLE charges FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON charges
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,122 @@
+-- Indian billing: GST invoices and Ayushman Bharat PM-JAY packages
+-- Migration: 20231017000043_india_billing.sql
+
+-- A tenant's GST registrations, one per state it supplies from. Invoices
+-- issued under a registration are numbered in its own series.
+CREATE TABLE gst_registrations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    gstin CHAR(15) NOT NULL,
+    legal_name VARCHAR(255) NOT NULL,
+    address TEXT,
+    -- Leads the numbers of the registration's invoices, e.g. INV
+    invoice_prefix VARCHAR(4) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_invoice_prefix CHECK (invoice_prefix ~ '^[A-Z0-9]{1,4}$'),
+    UNIQUE (tenant_id, gstin)
+);
+
+CREATE TRIGGER update_gst_registrations_updated_at BEFORE UPDATE ON gst_registrations FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Next invoice number of a registration's series in a financial year
+-- (April to March). GST requires numbers consecutive and unique within the
+-- financial year, so the series restarts at 1 each year.
+CREATE TABLE gst_invoice_series (
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    registration_id UUID NOT NULL REFERENCES gst_registrations(id),
+    financial_year CHAR(7) NOT NULL,
+    next_number INTEGER NOT NULL DEFAULT 1,
+
+    PRIMARY KEY (registration_id, financial_year)
+);
+
+-- Invoices, with their lines and tax as issued. Amounts are in paise.
+-- Supplies within the supplier's state are taxed CGST and SGST, half the
+-- rate each; supplies to another state IGST at the full rate. A document
+-- whose lines are all exempt, as most healthcare services are, is a bill of
+-- supply rather than a tax invoice. Issued invoices are not changed, only
+-- cancelled.
+CREATE TABLE gst_invoices (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    registration_id UUID NOT NULL REFERENCES gst_registrations(id),
+    invoice_number VARCHAR(16) NOT NULL,
+    document_type VARCHAR(20) NOT NULL,
+    invoice_date DATE NOT NULL,
+    financial_year CHAR(7) NOT NULL,
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    encounter_id UUID,
+    recipient_name VARCHAR(255) NOT NULL,
+    -- Set for supplies to a registered recipient, e.g. an insurer or employer
+    recipient_gstin CHAR(15),
+    place_of_supply CHAR(2) NOT NULL,
+    supply_type VARCHAR(20) NOT NULL,
+    lines JSONB NOT NULL,
+    taxable_value BIGINT NOT NULL,
+    cgst BIGINT NOT NULL,
+    sgst BIGINT NOT NULL,
+    igst BIGINT NOT NULL,
+    total BIGINT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'issued',
+    cancel_reason TEXT,
+    issued_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_gst_document_type CHECK (document_type IN ('tax_invoice', 'bill_of_supply')),
+    CONSTRAINT valid_gst_supply_type CHECK (supply_type IN ('intra_state', 'inter_state')),
+    CONSTRAINT valid_gst_invoice_status CHECK (status IN ('issued', 'cancelled')),
+    CONSTRAINT valid_gst_split CHECK (
+        (supply_type = 'intra_state' AND igst = 0) OR (supply_type = 'inter_state' AND cgst = 0 AND sgst = 0)
+    ),
+    CONSTRAINT valid_gst_total CHECK (total = taxable_value + cgst + sgst + igst),
+    CONSTRAINT cancelled_invoice_reason CHECK (status <> 'cancelled' OR cancel_reason IS NOT NULL),
+    UNIQUE (registration_id, invoice_number)
+);
+
+CREATE INDEX idx_gst_invoices_patient ON gst_invoices (patient_id);
+CREATE INDEX idx_gst_invoices_date ON gst_invoices (invoice_date);
+
+CREATE TRIGGER update_gst_invoices_updated_at BEFORE UPDATE ON gst_invoices FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE gst_registrations ENABLE ROW LEVEL SECURITY;
+ALTER TABLE gst_registrations FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON gst_registrations
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE gst_invoice_series ENABLE ROW LEVEL SECURITY;
+ALTER TABLE gst_invoice_series FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON gst_invoice_series
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE gst_invoices ENABLE ROW LEVEL SECURITY;
+ALTER TABLE gst_invoices FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON gst_invoices
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- Ayushman Bharat PM-JAY health benefit packages, from the National Health
+-- Authority's package master. The master is national, so it is not
+-- tenant-scoped; base_rate is the national rate in paise.
+CREATE TABLE pmjay_packages (
+    code VARCHAR(10) PRIMARY KEY,
+    specialty VARCHAR(100) NOT NULL,
+    name TEXT NOT NULL,
+    base_rate BIGINT NOT NULL,
+    preauth_required BOOLEAN NOT NULL DEFAULT true,
+    day_care BOOLEAN NOT NULL DEFAULT false,
+    hbp_version VARCHAR(20) NOT NULL,
+    effective_date DATE NOT NULL,
+    termination_date DATE,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_pmjay_rate CHECK (base_rate >= 0),
+    CONSTRAINT valid_pmjay_dates CHECK (termination_date IS NULL OR termination_date >= effective_date)
+);
+
+CREATE INDEX idx_pmjay_packages_specialty ON pmjay_packages (specialty);
@@ -0,0 +1,88 @@
+-- Payer contracts: fee schedules and remittance variance
+-- Migration: 20231017000044_payer_contracts.sql
+
+-- Fee schedules of payer contracts, each for one plan of a payer or, with no
+-- plan, all of them; a payer may have several, taking effect in turn.
+-- percent_of_charges, in hundredths of a percent, prices codes the schedule
+-- has no rate for.
+CREATE TABLE fee_schedules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    payer_id VARCHAR(80) NOT NULL,
+    payer_name VARCHAR(255) NOT NULL,
+    plan VARCHAR(80),
+    name VARCHAR(255) NOT NULL,
+    effective_date DATE NOT NULL,
+    termination_date DATE,
+    percent_of_charges INTEGER,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_fee_schedule_dates CHECK (termination_date IS NULL OR termination_date >= effective_date),
+    CONSTRAINT valid_percent_of_charges CHECK (percent_of_charges BETWEEN 0 AND 10000),
+    UNIQUE (tenant_id, payer_id, name)
+);
+
+CREATE INDEX idx_fee_schedules_payer ON fee_schedules (payer_id, plan);
+
+CREATE TRIGGER update_fee_schedules_updated_at BEFORE UPDATE ON fee_schedules FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- A schedule's rates per unit, each from its effective date. modifier is ''
+-- for the code billed without one. Rates are in the smallest unit of the
+-- currency, e.g. cents.
+CREATE TABLE fee_schedule_rates (
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    fee_schedule_id UUID NOT NULL REFERENCES fee_schedules(id),
+    code_system VARCHAR(10) NOT NULL,
+    code VARCHAR(5) NOT NULL,
+    modifier VARCHAR(2) NOT NULL DEFAULT '',
+    rate BIGINT NOT NULL,
+    effective_date DATE NOT NULL,
+    termination_date DATE,
+
+    PRIMARY KEY (fee_schedule_id, code_system, code, modifier, effective_date),
+    CONSTRAINT valid_fee_rate_code_system CHECK (code_system IN ('cpt', 'hcpcs')),
+    CONSTRAINT valid_fee_rate CHECK (rate >= 0),
+    CONSTRAINT valid_fee_rate_dates CHECK (termination_date IS NULL OR termination_date >= effective_date)
+);
+
+-- Remittance lines posted against charges, as 835 SVC and CAS segments give
+-- them. A reversal is posted as a line of negative amounts. A charge's
+-- actual allowed amount from a payer is what the payer paid plus the
+-- patient's share (group PR) over all its lines, compared with the contract
+-- when variance is reported.
+CREATE TABLE charge_payments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    charge_id UUID NOT NULL REFERENCES charges(id),
+    payer_id VARCHAR(80) NOT NULL,
+    plan VARCHAR(80),
+    payer_claim_id VARCHAR(50),
+    paid_date DATE NOT NULL,
+    paid BIGINT NOT NULL,
+    patient_responsibility BIGINT NOT NULL,
+    adjustments JSONB NOT NULL DEFAULT '[]',
+    posted_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_charge_payments_charge ON charge_payments (charge_id);
+CREATE INDEX idx_charge_payments_payer ON charge_payments (payer_id, paid_date);
+
+ALTER TABLE fee_schedules ENABLE ROW LEVEL SECURITY;
+ALTER TABLE fee_schedules FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON fee_schedules
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE fee_schedule_rates ENABLE ROW LEVEL SECURITY;
+ALTER TABLE fee_schedule_rates FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON fee_schedule_rates
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE charge_payments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE charge_payments FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON charge_payments
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
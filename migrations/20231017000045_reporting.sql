@@ -0,0 +1,177 @@
+-- Reporting: de-identified views, saved report definitions and their runs
+-- Migration: 20231017000045_reporting.sql
+
+-- Secret the pseudonymous keys of reporting views are derived with, drawn
+-- once per deployment. One row, read only by reporting_pseudonym(); not
+-- tenant-scoped, as keys must not be comparable with another deployment's.
+CREATE TABLE reporting_pseudonym_secret (
+    secret TEXT NOT NULL
+);
+
+INSERT INTO reporting_pseudonym_secret (secret)
+VALUES (encode(gen_random_bytes(32), 'hex'));
+
+-- Stable pseudonymous key of a record, so rows of one patient can be linked
+-- across views without revealing the record's ID. Qualified, as reports
+-- run with only the reporting schema on their search path.
+CREATE OR REPLACE FUNCTION reporting_pseudonym(p_id UUID) RETURNS TEXT AS $$
+    SELECT md5(secret || p_id::text) FROM public.reporting_pseudonym_secret
+$$ LANGUAGE sql STABLE;
+
+-- Views reports query. They carry no names, contact details, addresses
+-- below the state, free text or record IDs; dates are cut to the month
+-- and birth dates to the year, and ages over 89 are not given. The views
+-- read the shared tables through their row-level security, so reports see
+-- only the tenant they run as; tenants isolated in a schema of their own
+-- are not covered.
+CREATE SCHEMA IF NOT EXISTS reporting;
+
+-- age_band is the lower bound of the patient's five-year age band, 90 for
+-- 90 and over
+CREATE VIEW reporting.patients WITH (security_barrier) AS
+SELECT
+    reporting_pseudonym(p.id) AS patient_key,
+    p.gender,
+    CASE WHEN a.age < 90 THEN date_part('year', p.birth_date)::int END AS birth_year,
+    CASE WHEN a.age >= 90 THEN 90 ELSE a.age / 5 * 5 END AS age_band,
+    COALESCE(p.deceased, false) AS deceased,
+    p.active,
+    p.address->0->>'state' AS state,
+    p.address->0->>'country' AS country
+FROM patients p
+CROSS JOIN LATERAL (SELECT date_part('year', age(p.birth_date))::int AS age) a;
+
+CREATE VIEW reporting.encounters WITH (security_barrier) AS
+SELECT
+    reporting_pseudonym(e.id) AS encounter_key,
+    reporting_pseudonym(e.subject) AS patient_key,
+    e.status,
+    e.class->>'code' AS class,
+    e.service_type->'coding'->0->>'code' AS service_type,
+    e.service_provider::text AS organization_id,
+    date_trunc('month', (e.period->>'start')::timestamptz)::date AS start_month,
+    date_trunc('month', (e.period->>'end')::timestamptz)::date AS end_month,
+    (e.period->>'end')::timestamptz::date - (e.period->>'start')::timestamptz::date AS length_of_stay,
+    e.hospitalization->'dischargeDisposition'->'coding'->0->>'code' AS discharge_disposition
+FROM encounters e;
+
+CREATE VIEW reporting.appointments WITH (security_barrier) AS
+SELECT
+    reporting_pseudonym(a.id) AS appointment_key,
+    reporting_pseudonym(a.patient_id) AS patient_key,
+    a.practitioner_id::text AS practitioner_id,
+    a.status,
+    a.service_type->0->'coding'->0->>'code' AS service_type,
+    date_trunc('month', a.start_time)::date AS start_month,
+    a.minutes_duration
+FROM appointments a;
+
+CREATE VIEW reporting.conditions WITH (security_barrier) AS
+SELECT
+    reporting_pseudonym(c.patient_id) AS patient_key,
+    reporting_pseudonym(c.encounter_id) AS encounter_key,
+    c.snomed_code,
+    c.category,
+    c.clinical_status,
+    c.verification_status,
+    date_part('year', c.onset_date_time)::int AS onset_year,
+    date_trunc('month', c.recorded_date)::date AS recorded_month
+FROM conditions c
+WHERE c.deleted_at IS NULL;
+
+CREATE VIEW reporting.charges WITH (security_barrier) AS
+SELECT
+    reporting_pseudonym(c.id) AS charge_key,
+    reporting_pseudonym(c.patient_id) AS patient_key,
+    reporting_pseudonym(c.encounter_id) AS encounter_key,
+    c.code_system,
+    c.code,
+    array_to_string(c.modifiers, ' ') AS modifiers,
+    c.units,
+    c.unit_price,
+    c.units * c.unit_price AS amount,
+    date_trunc('month', c.service_date)::date AS service_month,
+    c.status
+FROM charges c;
+
+-- Saved reports: a query over the reporting views, either as a query tree
+-- or as SQL, its declared parameters, and the roles whose members may run
+-- it. A scheduled report runs at schedule_hour (UTC) each day, each Monday
+-- or on the first of each month, with schedule_parameters, its output kept
+-- as a run.
+CREATE TABLE report_definitions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    kind VARCHAR(10) NOT NULL,
+    query JSONB,
+    sql_text TEXT,
+    parameters JSONB NOT NULL DEFAULT '[]',
+    allowed_roles TEXT[] NOT NULL,
+    schedule_frequency VARCHAR(10),
+    schedule_hour SMALLINT,
+    schedule_format VARCHAR(10),
+    schedule_parameters JSONB NOT NULL DEFAULT '{}',
+    next_run_at TIMESTAMP WITH TIME ZONE,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_report_kind CHECK (
+        (kind = 'query' AND query IS NOT NULL AND sql_text IS NULL)
+        OR (kind = 'sql' AND sql_text IS NOT NULL AND query IS NULL)
+    ),
+    CONSTRAINT valid_report_roles CHECK (cardinality(allowed_roles) > 0),
+    CONSTRAINT valid_report_schedule CHECK (
+        (schedule_frequency IS NULL AND schedule_hour IS NULL AND schedule_format IS NULL)
+        OR (
+            schedule_frequency IN ('daily', 'weekly', 'monthly')
+            AND schedule_hour BETWEEN 0 AND 23
+            AND schedule_format IN ('csv', 'pdf', 'xlsx')
+        )
+    ),
+    UNIQUE (tenant_id, name)
+);
+
+CREATE INDEX idx_report_definitions_due ON report_definitions (next_run_at) WHERE is_active AND next_run_at IS NOT NULL;
+
+CREATE TRIGGER update_report_definitions_updated_at BEFORE UPDATE ON report_definitions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Each run of a report, manual or scheduled, with the parameters it ran
+-- with. Scheduled runs keep their output to be downloaded.
+CREATE TABLE report_runs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    report_id UUID NOT NULL REFERENCES report_definitions(id),
+    source VARCHAR(10) NOT NULL,
+    parameters JSONB NOT NULL DEFAULT '{}',
+    format VARCHAR(10) NOT NULL,
+    status VARCHAR(10) NOT NULL,
+    row_count INTEGER,
+    truncated BOOLEAN NOT NULL DEFAULT false,
+    output BYTEA,
+    error TEXT,
+    run_by UUID,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    finished_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_report_run_source CHECK (source IN ('manual', 'scheduled')),
+    CONSTRAINT valid_report_run_format CHECK (format IN ('json', 'csv', 'pdf', 'xlsx')),
+    CONSTRAINT valid_report_run_status CHECK (status IN ('succeeded', 'failed'))
+);
+
+CREATE INDEX idx_report_runs_report ON report_runs (report_id, started_at DESC);
+
+ALTER TABLE report_definitions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE report_definitions FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON report_definitions
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE report_runs ENABLE ROW LEVEL SECURITY;
+ALTER TABLE report_runs FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON report_runs
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,134 @@
+-- Analytics: precomputed KPI aggregates for the dashboard
+-- Migration: 20231017000046_analytics.sql
+
+-- A department is the organization an encounter is served by. Appointments
+-- count towards the organization of the user who is the appointment's
+-- practitioner, and charges towards the department of their encounter.
+
+-- Beds each department has, for bed occupancy
+CREATE TABLE department_beds (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    department_id UUID NOT NULL REFERENCES organizations(id),
+    beds INTEGER NOT NULL,
+    updated_by UUID,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_department_beds CHECK (beds >= 0),
+    UNIQUE (tenant_id, department_id)
+);
+
+CREATE TRIGGER update_department_beds_updated_at BEFORE UPDATE ON department_beds
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE department_beds ENABLE ROW LEVEL SECURITY;
+ALTER TABLE department_beds FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON department_beds
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+-- Inpatient stays, by UTC day. A stay occupies a bed until it is
+-- discharged, or until today while the patient is still in.
+CREATE VIEW analytics_inpatient_stays AS
+SELECT
+    e.tenant_id,
+    e.service_provider AS department_id,
+    e.status,
+    ((e.period->>'start')::timestamptz AT TIME ZONE 'UTC')::date AS admitted_on,
+    ((e.period->>'end')::timestamptz AT TIME ZONE 'UTC')::date AS discharged_on,
+    COALESCE(
+        ((e.period->>'end')::timestamptz AT TIME ZONE 'UTC')::date,
+        CASE WHEN e.status IN ('arrived', 'triaged', 'in-progress', 'onleave') THEN CURRENT_DATE END
+    ) AS occupied_until
+FROM encounters e
+WHERE e.class->>'code' IN ('IMP', 'ACUTE', 'NONAC')
+    AND e.status NOT IN ('planned', 'cancelled', 'entered-in-error')
+    AND e.period->>'start' IS NOT NULL;
+
+-- The materialized views hold every tenant's rows, computed by the refresh
+-- job with the tenant policy bypassed. Materialized views have no row-level
+-- security, so every query of them filters on tenant_id itself. The unique
+-- indexes let them be refreshed concurrently, without blocking readers.
+
+CREATE MATERIALIZED VIEW analytics_daily_admissions AS
+SELECT tenant_id, department_id, admitted_on AS day, count(*)::int AS admissions
+FROM analytics_inpatient_stays
+GROUP BY tenant_id, department_id, admitted_on;
+
+CREATE UNIQUE INDEX idx_analytics_daily_admissions ON analytics_daily_admissions (tenant_id, department_id, day);
+
+-- Midnight census: the beds occupied at the end of each day, over the last
+-- two years
+CREATE MATERIALIZED VIEW analytics_daily_census AS
+SELECT s.tenant_id, s.department_id, d::date AS day, count(*)::int AS occupied_beds
+FROM analytics_inpatient_stays s
+CROSS JOIN LATERAL generate_series(
+    GREATEST(s.admitted_on, CURRENT_DATE - 730),
+    s.occupied_until - 1,
+    interval '1 day'
+) d
+WHERE s.occupied_until > CURRENT_DATE - 730
+GROUP BY s.tenant_id, s.department_id, d::date;
+
+CREATE UNIQUE INDEX idx_analytics_daily_census ON analytics_daily_census (tenant_id, department_id, day);
+
+-- Discharges and the days their stays lasted, a stay admitted and
+-- discharged the same day counting as one
+CREATE MATERIALIZED VIEW analytics_daily_discharges AS
+SELECT
+    tenant_id,
+    department_id,
+    discharged_on AS day,
+    count(*)::int AS discharges,
+    sum(GREATEST(discharged_on - admitted_on, 1))::bigint AS patient_days
+FROM analytics_inpatient_stays
+WHERE status = 'finished' AND discharged_on IS NOT NULL
+GROUP BY tenant_id, department_id, discharged_on;
+
+CREATE UNIQUE INDEX idx_analytics_daily_discharges ON analytics_daily_discharges (tenant_id, department_id, day);
+
+-- Outcomes of appointments by the day they were for
+CREATE MATERIALIZED VIEW analytics_daily_appointments AS
+SELECT
+    a.tenant_id,
+    u.organization_id AS department_id,
+    (a.start_time AT TIME ZONE 'UTC')::date AS day,
+    count(*) FILTER (WHERE a.status IN ('arrived', 'checked-in', 'fulfilled'))::int AS attended,
+    count(*) FILTER (WHERE a.status = 'noshow')::int AS no_shows,
+    count(*) FILTER (WHERE a.status = 'cancelled')::int AS cancelled
+FROM appointments a
+LEFT JOIN LATERAL (
+    SELECT organization_id
+    FROM users
+    WHERE practitioner_id = a.practitioner_id AND tenant_id = a.tenant_id
+    ORDER BY created_at
+    LIMIT 1
+) u ON true
+WHERE a.start_time IS NOT NULL
+GROUP BY a.tenant_id, u.organization_id, (a.start_time AT TIME ZONE 'UTC')::date;
+
+CREATE UNIQUE INDEX idx_analytics_daily_appointments ON analytics_daily_appointments (tenant_id, department_id, day);
+
+-- Charges captured, in the smallest unit of the currency, by date of
+-- service
+CREATE MATERIALIZED VIEW analytics_daily_revenue AS
+SELECT
+    c.tenant_id,
+    e.service_provider AS department_id,
+    c.service_date AS day,
+    count(*)::int AS charges,
+    sum(c.units::bigint * c.unit_price)::bigint AS amount
+FROM charges c
+LEFT JOIN encounters e ON e.id = c.encounter_id
+WHERE c.status = 'active'
+GROUP BY c.tenant_id, e.service_provider, c.service_date;
+
+CREATE UNIQUE INDEX idx_analytics_daily_revenue ON analytics_daily_revenue (tenant_id, department_id, day);
+
+-- When each materialized view was last refreshed. Not tenant-scoped, as
+-- the views are refreshed for every tenant at once.
+CREATE TABLE analytics_refreshes (
+    view_name VARCHAR(100) PRIMARY KEY,
+    refreshed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    duration_ms INTEGER NOT NULL
+);
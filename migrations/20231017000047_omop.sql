@@ -0,0 +1,20 @@
+-- OMOP CDM export: the concepts of the OMOP standardized vocabularies
+-- Migration: 20231017000047_omop.sql
+
+-- Concepts of source codes, loaded from the vocabularies downloaded from
+-- Athena: concept_id from CONCEPT, and standard_concept_id the concept its
+-- 'Maps to' relationship in CONCEPT_RELATIONSHIP leads to, 0 for none. Not
+-- tenant-scoped, as the vocabularies are the same for every tenant.
+CREATE TABLE omop_concepts (
+    vocabulary_id VARCHAR(20) NOT NULL,
+    concept_code VARCHAR(50) NOT NULL,
+    concept_id INTEGER NOT NULL,
+    standard_concept_id INTEGER NOT NULL DEFAULT 0,
+    concept_name VARCHAR(255),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (vocabulary_id, concept_code)
+);
+
+CREATE TRIGGER update_omop_concepts_updated_at BEFORE UPDATE ON omop_concepts
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
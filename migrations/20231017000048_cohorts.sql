@@ -0,0 +1,49 @@
+-- Population health cohorts: saved definitions and their member lists
+-- Migration: 20231017000048_cohorts.sql
+
+-- A cohort as a tree of demographic, diagnosis, lab value and medication
+-- criteria, evaluated against the clinical tables into cohort_members
+CREATE TABLE cohort_definitions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    criteria JSONB NOT NULL,
+    member_count INTEGER,
+    last_evaluated_at TIMESTAMP WITH TIME ZONE,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_cohort_evaluation CHECK ((member_count IS NULL) = (last_evaluated_at IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_cohort_definitions_name ON cohort_definitions (tenant_id, name) WHERE is_active;
+
+CREATE TRIGGER update_cohort_definitions_updated_at BEFORE UPDATE ON cohort_definitions FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Patients meeting a cohort's criteria when it was last evaluated. A
+-- member keeps the time it joined while it goes on meeting them.
+CREATE TABLE cohort_members (
+    cohort_id UUID NOT NULL REFERENCES cohort_definitions(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    added_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (cohort_id, patient_id)
+);
+
+CREATE INDEX idx_cohort_members_patient ON cohort_members (patient_id);
+
+ALTER TABLE cohort_definitions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE cohort_definitions FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON cohort_definitions
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE cohort_members ENABLE ROW LEVEL SECURITY;
+ALTER TABLE cohort_members FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON cohort_members
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,65 @@
+-- Clinical quality measures and their results
+-- Migration: 20231017000049_quality_measures.sql
+
+-- A proportion measure: its initial population, denominator, denominator
+-- exclusion and numerator, each as cohort criteria
+CREATE TABLE quality_measures (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    code VARCHAR(64) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    -- Canonical URL, for MeasureReports, and eCQM version-specific ID, for
+    -- QRDA III
+    url TEXT,
+    version_specific_id VARCHAR(64),
+    improvement_notation VARCHAR(10) NOT NULL,
+    period_days INTEGER NOT NULL DEFAULT 365,
+    populations JSONB NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_quality_measure_code UNIQUE (tenant_id, code),
+    CONSTRAINT valid_quality_measure_notation CHECK (improvement_notation IN ('increase', 'decrease')),
+    CONSTRAINT valid_quality_measure_period CHECK (period_days BETWEEN 1 AND 3660)
+);
+
+CREATE TRIGGER update_quality_measures_updated_at BEFORE UPDATE ON quality_measures FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Each evaluation of a measure, over every patient or one cohort's members
+CREATE TABLE quality_measure_results (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    measure_id UUID NOT NULL REFERENCES quality_measures(id),
+    cohort_id UUID REFERENCES cohort_definitions(id),
+    period_start DATE NOT NULL,
+    period_end DATE NOT NULL,
+    initial_population BIGINT NOT NULL,
+    denominator BIGINT NOT NULL,
+    denominator_exclusion BIGINT NOT NULL,
+    numerator BIGINT NOT NULL,
+    evaluated_by UUID,
+    evaluated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_quality_measure_result_period CHECK (period_end >= period_start),
+    CONSTRAINT valid_quality_measure_result_counts CHECK (
+        denominator <= initial_population
+        AND denominator_exclusion <= denominator
+        AND numerator <= denominator - denominator_exclusion
+    )
+);
+
+CREATE INDEX idx_quality_measure_results_measure ON quality_measure_results (measure_id, evaluated_at DESC);
+
+ALTER TABLE quality_measures ENABLE ROW LEVEL SECURITY;
+ALTER TABLE quality_measures FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON quality_measures
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE quality_measure_results ENABLE ROW LEVEL SECURITY;
+ALTER TABLE quality_measure_results FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON quality_measure_results
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
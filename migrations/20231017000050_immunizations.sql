@@ -0,0 +1,54 @@
+-- Immunizations given and the schedules they are forecast against
+-- Migration: 20231017000050_immunizations.sql
+
+-- A dose given, or recorded as not given, coded as system|code, usually CVX
+CREATE TABLE immunizations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    vaccine_system VARCHAR(255) NOT NULL,
+    vaccine_code VARCHAR(50) NOT NULL,
+    vaccine_display VARCHAR(255),
+    occurrence_date DATE NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'completed',
+    lot_number VARCHAR(100),
+    note TEXT,
+    recorded_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_immunization_status CHECK (status IN ('completed', 'not-done', 'entered-in-error'))
+);
+
+CREATE INDEX idx_immunizations_patient ON immunizations (patient_id, occurrence_date);
+
+CREATE TRIGGER update_immunizations_updated_at BEFORE UPDATE ON immunizations FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- A tenant's own schedule, which takes the place of a built-in schedule of
+-- the same code
+CREATE TABLE immunization_schedules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    code VARCHAR(50) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    series JSONB NOT NULL,
+    updated_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_immunization_schedule_code UNIQUE (tenant_id, code)
+);
+
+CREATE TRIGGER update_immunization_schedules_updated_at BEFORE UPDATE ON immunization_schedules FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE immunizations ENABLE ROW LEVEL SECURITY;
+ALTER TABLE immunizations FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON immunizations
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE immunization_schedules ENABLE ROW LEVEL SECURITY;
+ALTER TABLE immunization_schedules FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON immunization_schedules
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,42 @@
+-- Video visits, held as encounters of the virtual class
+-- Migration: 20231017000051_telemedicine.sql
+
+-- A video visit between a patient and a practitioner, with where each is
+-- allowed to practise and the patient's consent to recording
+CREATE TABLE telemedicine_visits (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    encounter_id UUID NOT NULL REFERENCES encounters(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    practitioner_id UUID NOT NULL REFERENCES practitioners(id),
+    reason TEXT,
+    scheduled_start TIMESTAMP WITH TIME ZONE NOT NULL,
+    scheduled_end TIMESTAMP WITH TIME ZONE,
+    patient_state VARCHAR(10),
+    practitioner_licenses TEXT[] NOT NULL DEFAULT '{}',
+    status VARCHAR(20) NOT NULL DEFAULT 'planned',
+    video_room VARCHAR(100) NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE,
+    ended_at TIMESTAMP WITH TIME ZONE,
+    recording_consent JSONB,
+    recording_started_at TIMESTAMP WITH TIME ZONE,
+    created_by UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_telemedicine_visit_status CHECK (status IN ('planned', 'in-progress', 'finished', 'cancelled')),
+    CONSTRAINT recorded_with_consent CHECK (
+        recording_started_at IS NULL OR (recording_consent->>'given')::boolean
+    )
+);
+
+CREATE INDEX idx_telemedicine_visits_patient ON telemedicine_visits (patient_id, scheduled_start);
+CREATE INDEX idx_telemedicine_visits_practitioner ON telemedicine_visits (practitioner_id, scheduled_start);
+
+CREATE TRIGGER update_telemedicine_visits_updated_at BEFORE UPDATE ON telemedicine_visits FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+ALTER TABLE telemedicine_visits ENABLE ROW LEVEL SECURITY;
+ALTER TABLE telemedicine_visits FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON telemedicine_visits
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,50 @@
+-- Video sessions issued for telemedicine visits, and who joined and left
+-- Migration: 20231017000052_telemedicine_sessions.sql
+
+-- A short-lived token issued to join a visit's room, bound to the visit's
+-- encounter; the token itself is not kept
+CREATE TABLE telemedicine_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    visit_id UUID NOT NULL REFERENCES telemedicine_visits(id),
+    encounter_id UUID NOT NULL REFERENCES encounters(id),
+    identity VARCHAR(100) NOT NULL,
+    role VARCHAR(20) NOT NULL,
+    provider VARCHAR(20) NOT NULL,
+    room VARCHAR(100) NOT NULL,
+    issued_to UUID NOT NULL,
+    issued_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_telemedicine_session_role CHECK (role IN ('clinician', 'patient'))
+);
+
+CREATE INDEX idx_telemedicine_sessions_visit ON telemedicine_sessions (visit_id);
+
+-- A participant joining or leaving a visit through a session
+CREATE TABLE telemedicine_session_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    session_id UUID NOT NULL REFERENCES telemedicine_sessions(id),
+    visit_id UUID NOT NULL REFERENCES telemedicine_visits(id),
+    event VARCHAR(10) NOT NULL,
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    recorded_by UUID,
+
+    CONSTRAINT valid_telemedicine_session_event CHECK (event IN ('joined', 'left'))
+);
+
+CREATE INDEX idx_telemedicine_session_events_visit ON telemedicine_session_events (visit_id, occurred_at);
+
+ALTER TABLE telemedicine_sessions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE telemedicine_sessions FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON telemedicine_sessions
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE telemedicine_session_events ENABLE ROW LEVEL SECURITY;
+ALTER TABLE telemedicine_session_events FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON telemedicine_session_events
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
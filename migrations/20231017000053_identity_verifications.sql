@@ -0,0 +1,61 @@
+-- Demographic verification of patients against their ABHA or Aadhaar
+-- Migration: 20231017000053_identity_verifications.sql
+
+-- A patient's consent, or refusal, to their demographics being verified
+-- with the authority that issued their ID. ID numbers are kept only as a
+-- hash, so that Aadhaar numbers are never stored.
+CREATE TABLE identity_verification_consents (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID REFERENCES patients(id),
+    document VARCHAR(10) NOT NULL,
+    number_hash CHAR(64) NOT NULL,
+    granted BOOLEAN NOT NULL,
+    method VARCHAR(20) NOT NULL,
+    language VARCHAR(20),
+    consenter VARCHAR(200),
+    captured_by UUID NOT NULL,
+    captured_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT valid_identity_consent_document CHECK (document IN ('abha', 'aadhaar'))
+);
+
+CREATE INDEX idx_identity_verification_consents_number ON identity_verification_consents (number_hash);
+
+-- Each verification asked for, whether the authority was called or an
+-- earlier answer for the same demographics was reused
+CREATE TABLE identity_verifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID REFERENCES patients(id),
+    document VARCHAR(10) NOT NULL,
+    number_hash CHAR(64) NOT NULL,
+    masked_number VARCHAR(20) NOT NULL,
+    cache_key CHAR(64) NOT NULL,
+    outcome VARCHAR(10) NOT NULL,
+    reference VARCHAR(100),
+    detail TEXT,
+    cached BOOLEAN NOT NULL DEFAULT FALSE,
+    consent_id UUID NOT NULL REFERENCES identity_verification_consents(id),
+    verified_by UUID NOT NULL,
+    verified_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    CONSTRAINT valid_identity_verification_document CHECK (document IN ('abha', 'aadhaar')),
+    CONSTRAINT valid_identity_verification_outcome CHECK (outcome IN ('verified', 'mismatch'))
+);
+
+CREATE INDEX idx_identity_verifications_cache ON identity_verifications (cache_key, expires_at DESC);
+CREATE INDEX idx_identity_verifications_patient ON identity_verifications (patient_id, verified_at DESC);
+
+ALTER TABLE identity_verification_consents ENABLE ROW LEVEL SECURITY;
+ALTER TABLE identity_verification_consents FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON identity_verification_consents
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
+
+ALTER TABLE identity_verifications ENABLE ROW LEVEL SECURITY;
+ALTER TABLE identity_verifications FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON identity_verifications
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -0,0 +1,34 @@
+-- Smart card and fingerprint login on shared clinical workstations
+-- Migration: 20231017000054_workstation_credentials.sql
+
+-- PIV smart cards and match-on-device fingerprints enrolled for a user.
+-- Smart cards are enrolled under the SHA-256 of their PIV Authentication
+-- certificate, which is kept to verify signatures; fingerprints under their
+-- device and template, with the device's P-256 key.
+CREATE TABLE workstation_credentials (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    kind VARCHAR(20) NOT NULL,
+    credential_id VARCHAR(255) NOT NULL,
+    public_key BYTEA NOT NULL,
+    name VARCHAR(255),
+    created_by UUID NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    revoked_by UUID,
+
+    CONSTRAINT valid_workstation_credential_kind CHECK (kind IN ('smart-card', 'fingerprint'))
+);
+
+-- A credential logs in one user at a time; revoked ones may be re-enrolled
+CREATE UNIQUE INDEX idx_workstation_credentials_active
+    ON workstation_credentials (kind, credential_id) WHERE revoked_at IS NULL;
+CREATE INDEX idx_workstation_credentials_user ON workstation_credentials (user_id) WHERE revoked_at IS NULL;
+
+ALTER TABLE workstation_credentials ENABLE ROW LEVEL SECURITY;
+ALTER TABLE workstation_credentials FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON workstation_credentials
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
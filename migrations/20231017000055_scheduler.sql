@@ -0,0 +1,50 @@
+-- Scheduler for background jobs
+-- Migration: 20231017000055_scheduler.sql
+
+-- Background jobs, such as purges and view refreshes, on cron schedules.
+-- Jobs run for the whole deployment under a system tenant, so they are not
+-- tenant-scoped. The schedule starts as the job's default and may be
+-- changed or the job disabled by platform admins.
+CREATE TABLE scheduled_jobs (
+    name VARCHAR(100) PRIMARY KEY,
+    description TEXT NOT NULL,
+    schedule VARCHAR(100) NOT NULL,
+    default_schedule VARCHAR(100) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    next_run_at TIMESTAMP WITH TIME ZONE,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_by UUID
+);
+
+CREATE INDEX idx_scheduled_jobs_due ON scheduled_jobs (next_run_at) WHERE enabled = true;
+
+-- Each run of a job, on schedule or started by an admin, and what it did
+CREATE TABLE scheduled_job_runs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    job_name VARCHAR(100) NOT NULL REFERENCES scheduled_jobs(name) ON DELETE CASCADE,
+    trigger VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    instance_id VARCHAR(255) NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP WITH TIME ZONE,
+    output JSONB,
+    error TEXT,
+    triggered_by UUID,
+
+    CONSTRAINT valid_scheduled_job_run_trigger CHECK (trigger IN ('schedule', 'manual')),
+    CONSTRAINT valid_scheduled_job_run_status CHECK (status IN ('running', 'succeeded', 'failed'))
+);
+
+CREATE INDEX idx_scheduled_job_runs_job ON scheduled_job_runs (job_name, started_at DESC);
+CREATE INDEX idx_scheduled_job_runs_running ON scheduled_job_runs (started_at) WHERE status = 'running';
+
+-- Leases electing the one server that runs scheduled jobs. The holder
+-- renews its lease while it runs; another server takes over once it lapses.
+CREATE TABLE scheduler_leases (
+    name VARCHAR(100) PRIMARY KEY,
+    holder VARCHAR(255) NOT NULL,
+    acquired_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,28 @@
+-- Record relationship changes under the relationship's tenant
+-- Migration: 20231017000056_authorization_changelog_tenant.sql
+
+-- Changes were recorded under the connection's tenant, which is the default
+-- tenant for maintenance jobs deactivating expired relationships across
+-- tenants. Record them under the tenant of the relationship instead, so
+-- that each tenant's Watch API consumers see their own removals.
+CREATE OR REPLACE FUNCTION record_authorization_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' AND NEW.is_active THEN
+        INSERT INTO authorization_changelog (
+            tenant_id, operation, resource_type, resource_id, relation, subject_type, subject_id, changed_by
+        ) VALUES (
+            NEW.tenant_id, 'added', NEW.resource_type, NEW.resource_id, NEW.relation, NEW.subject_type,
+            NEW.subject_id, NEW.created_by
+        );
+    ELSIF TG_OP = 'UPDATE' AND OLD.is_active AND NOT NEW.is_active THEN
+        INSERT INTO authorization_changelog (
+            tenant_id, operation, resource_type, resource_id, relation, subject_type, subject_id
+        ) VALUES (
+            NEW.tenant_id, 'removed', NEW.resource_type, NEW.resource_id, NEW.relation, NEW.subject_type,
+            NEW.subject_id
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
@@ -0,0 +1,69 @@
+-- Uniform soft delete, restore and retention purge of clinical resources
+-- Migration: 20231017000057_soft_delete.sql
+
+-- Patients were deleted by clearing `active` alone. Give them the
+-- `deleted_at` of the other clinical tables, and add the columns the
+-- appointment service already expects.
+ALTER TABLE patients
+ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE appointments
+ADD COLUMN IF NOT EXISTS comment TEXT,
+ADD COLUMN IF NOT EXISTS created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+-- Patients deleted so far were deleted when history recorded it, across
+-- every tenant
+SELECT set_config('app.bypass_tenant', 'on', true);
+UPDATE patients p
+SET deleted_at = COALESCE((
+        SELECT changed_at FROM resource_history
+        WHERE resource_type = 'Patient' AND resource_id = p.id AND operation = 'delete'
+        ORDER BY id DESC LIMIT 1
+    ), NOW())
+WHERE active = false AND deleted_at IS NULL;
+
+-- Who deleted a row, set from the connection's app.user_id when
+-- deleted_at is set and cleared again on restore
+CREATE OR REPLACE FUNCTION stamp_soft_delete() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.deleted_at IS NULL THEN
+        NEW.deleted_by := NULL;
+    ELSIF OLD.deleted_at IS NULL THEN
+        NEW.deleted_by := NULLIF(current_setting('app.user_id', true), '')::uuid;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOREACH t IN ARRAY ARRAY[
+        'patients', 'appointments', 'medical_records', 'conditions',
+        'vital_signs', 'care_plans', 'tasks', 'referrals'
+    ] LOOP
+        EXECUTE format('ALTER TABLE %I ADD COLUMN IF NOT EXISTS deleted_by UUID', t);
+        -- Listing and purging deleted rows read only the few that are deleted
+        EXECUTE format(
+            'CREATE INDEX IF NOT EXISTS %I ON %I (deleted_at) WHERE deleted_at IS NOT NULL',
+            'idx_' || t || '_deleted_at', t);
+        EXECUTE format(
+            'CREATE TRIGGER %I BEFORE UPDATE OF deleted_at ON %I FOR EACH ROW EXECUTE FUNCTION stamp_soft_delete()',
+            t || '_stamp_soft_delete', t);
+    END LOOP;
+END $$;
+
+-- History stays append-only, except that the retention purge removes the
+-- history of the resources it hard-deletes. It sets app.retention_purge for
+-- its own transaction only.
+CREATE OR REPLACE FUNCTION reject_history_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' AND COALESCE(current_setting('app.retention_purge', true), 'off') = 'on' THEN
+        RETURN OLD;
+    END IF;
+    RAISE EXCEPTION 'resource_history is append-only';
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,49 @@
+-- Provenance of resource versions
+-- Migration: 20231017000058_provenance.sql
+
+-- Besides who, when and why, record the organization the user acted on
+-- behalf of and the system the change came from. Versions recorded so far
+-- keep these empty.
+ALTER TABLE resource_history
+ADD COLUMN IF NOT EXISTS on_behalf_of UUID,
+ADD COLUMN IF NOT EXISTS source_system VARCHAR(255);
+
+CREATE INDEX IF NOT EXISTS idx_resource_history_on_behalf_of ON resource_history (on_behalf_of, changed_at DESC);
+
+-- Snapshot a resource row. The application sets app.user_id,
+-- app.change_reason and app.change_source on the connection for the
+-- request making the change; the organization is the user's own.
+-- TG_ARGV[0] is the FHIR resource type.
+CREATE OR REPLACE FUNCTION record_resource_history() RETURNS TRIGGER AS $$
+DECLARE
+    new_row JSONB := to_jsonb(NEW);
+    op VARCHAR(10) := 'update';
+    actor UUID := NULLIF(current_setting('app.user_id', true), '')::uuid;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        op := 'create';
+    ELSIF new_row->>'deleted_at' IS NOT NULL OR new_row->>'active' = 'false' THEN
+        -- Compare with the last recorded version rather than OLD, since the
+        -- deletion may have been written before the version was bumped
+        IF COALESCE((
+            SELECT operation FROM resource_history
+            WHERE resource_type = TG_ARGV[0] AND resource_id = NEW.id
+            ORDER BY id DESC LIMIT 1
+        ), '') <> 'delete' THEN
+            op := 'delete';
+        END IF;
+    END IF;
+
+    INSERT INTO resource_history (
+        tenant_id, resource_type, resource_id, version_id, operation, resource,
+        changed_by, change_reason, on_behalf_of, source_system
+    ) VALUES (
+        NEW.tenant_id, TG_ARGV[0], NEW.id, COALESCE(NEW.meta->>'version_id', '1'), op, new_row,
+        actor,
+        NULLIF(current_setting('app.change_reason', true), ''),
+        (SELECT organization_id FROM users WHERE id = actor),
+        NULLIF(current_setting('app.change_source', true), '')
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
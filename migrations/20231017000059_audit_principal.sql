@@ -0,0 +1,20 @@
+-- Authenticated principal of audit events
+-- Migration: 20231017000059_audit_principal.sql
+
+-- Besides the user, record the session they acted in and why they accessed
+-- the records, so access can be traced back to a sign-in and a purpose.
+ALTER TABLE audit_logs
+ADD COLUMN IF NOT EXISTS session_id VARCHAR(255),
+ADD COLUMN IF NOT EXISTS purpose_of_use VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_session ON audit_logs (session_id) WHERE session_id IS NOT NULL;
+
+-- Services log reads and writes with the FHIR-style create, read, update,
+-- delete and access event types alongside the category ones
+ALTER TABLE audit_logs DROP CONSTRAINT valid_event_type;
+ALTER TABLE audit_logs ADD CONSTRAINT valid_event_type CHECK (
+    event_type IN (
+        'patient-access', 'data-modification', 'authentication', 'export', 'system-access', 'compliance-violation',
+        'create', 'read', 'update', 'delete', 'access'
+    )
+);
@@ -0,0 +1,13 @@
+-- Confidentiality labels of records
+-- Migration: 20231017000060_security_labels.sql
+
+-- Labels such as VIP, psychiatric, HIV and substance use live in the FHIR
+-- meta.security of patients and medical records. Policies on labelled
+-- records limit access to holders of listed relations.
+ALTER TABLE authorization_policies DROP CONSTRAINT IF EXISTS authorization_policies_effect_check;
+ALTER TABLE authorization_policies ADD CONSTRAINT authorization_policies_effect_check CHECK (
+    effect IN (
+        'allow', 'deny', 'require_approval', 'require_second_factor', 'audit_only', 'time_limit', 'restrict',
+        'conditional', 'require_relations'
+    )
+);
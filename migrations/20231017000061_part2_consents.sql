@@ -0,0 +1,33 @@
+-- 42 CFR Part 2 consents to disclose substance use disorder records
+-- Migration: 20231017000061_part2_consents.sql
+
+-- A patient's written consent to disclose their substance use records: to
+-- whom, for which purposes, what information and until when. A consent
+-- without a recipient is a general designation, e.g. a single consent for
+-- treatment, payment and health care operations. Revoked consents are kept.
+CREATE TABLE part2_consents (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL DEFAULT current_tenant_id() REFERENCES tenants(id),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    recipient VARCHAR(255),
+    purposes TEXT[] NOT NULL,
+    information TEXT NOT NULL,
+    signed_by VARCHAR(200) NOT NULL,
+    signed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    recorded_by UUID NOT NULL,
+    recorded_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    revoked_by UUID,
+
+    CONSTRAINT part2_consent_has_purpose CHECK (cardinality(purposes) > 0),
+    CONSTRAINT part2_consent_expires_after_signing CHECK (expires_at > signed_at)
+);
+
+CREATE INDEX idx_part2_consents_patient ON part2_consents (patient_id, expires_at DESC) WHERE revoked_at IS NULL;
+
+ALTER TABLE part2_consents ENABLE ROW LEVEL SECURITY;
+ALTER TABLE part2_consents FORCE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON part2_consents
+    USING (tenant_bypass() OR tenant_id = current_tenant_id())
+    WITH CHECK (tenant_bypass() OR tenant_id = current_tenant_id());
@@ -1,13 +1,20 @@
 use anyhow::Result;
-use axum::{http::StatusCode, response::Json, routing::get, Router};
+use axum::{response::Json, routing::get, Router};
 use serde::Serialize;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 
 use hims_core_sdk::{
-    database::connection::Database,
-    modules::AppModules,
+    core::logger::HimsLogger,
+    database::{connection::Database, migration_status, run_migrations, DatabaseConfig},
+    modules::{
+        authorization::{AuthorizationConfig, HimsAuthorizationEngine},
+        events::{forward, EventBusConfig},
+        metrics::QUEUE_DEPTH_INTERVAL,
+        AppModules, EventBus,
+    },
 };
 
 #[derive(Serialize)]
@@ -29,11 +36,44 @@ async fn health() -> Json<HealthResponse> {
 /// Create the main application router with all modules
 async fn create_app() -> Result<Router> {
     // Initialize database connection
-    let database = Database::new().await?;
-    let db_pool = database.get_pool();
+    let database = Database::new(DatabaseConfig::default()).await?;
+    let db_pool = database.pool().clone();
     
-    // Initialize all application modules
-    let app_modules = Arc::new(AppModules::new(db_pool));
+    // Initialize all application modules, publishing domain events to the
+    // configured brokers
+    let event_config = EventBusConfig::from_env();
+    let events = EventBus::new(event_config.capacity);
+    for publisher in event_config.connect_publishers().await? {
+        forward(&events, publisher);
+    }
+    // Decide access on the authorization storage and policies the
+    // environment selects
+    let authorization =
+        Arc::new(HimsAuthorizationEngine::from_config(AuthorizationConfig::from_env(), Some(db_pool.clone())).await?);
+    let app_modules = Arc::new(AppModules::with_events(db_pool, events, authorization));
+
+    // Match resource changes against subscriptions and deliver notifications
+    app_modules.subscription.get_service().spawn(std::time::Duration::from_secs(2));
+    // Queue ADT messages for domain events on the interface engine, which
+    // sends them to HL7 destinations
+    app_modules.adt_feed.get_service().spawn(&app_modules.events);
+    // Keep integration channels current as admins change them
+    app_modules.integration.get_service().spawn();
+    // Import result files dropped on SFTP channels
+    app_modules.integration.get_file_drop().spawn(std::time::Duration::from_secs(60));
+    app_modules.interface_engine.get_service().spawn(std::time::Duration::from_secs(2));
+    // Queue webhook deliveries for domain events and send them signed
+    app_modules.webhook.get_service().listen(&app_modules.events);
+    app_modules.webhook.get_service().spawn(std::time::Duration::from_secs(2));
+    // Open notifiable disease case reports as diagnoses are recorded
+    app_modules.surveillance.get_service().listen(&app_modules.events);
+    // Run scheduled reports as they fall due
+    app_modules.report.get_service().spawn(std::time::Duration::from_secs(60));
+    // Run background jobs, such as purging idempotency keys and refreshing
+    // the dashboard's KPIs, on the server elected to lead
+    app_modules.scheduler.get_service().spawn();
+    // Read the interface engine, webhook and event queue depths for /metrics
+    app_modules.metrics.get_service().spawn(QUEUE_DEPTH_INTERVAL);
     
     // Create the main router
     let app = Router::new()
@@ -48,13 +88,35 @@ async fn create_app() -> Result<Router> {
     Ok(app)
 }
 
+/// `hims-server migrate [run|status]`: apply pending migrations, or list
+/// which embedded migrations the database has, then exit
+async fn migrate_command(action: Option<&str>) -> Result<()> {
+    let database = Database::new(DatabaseConfig::default()).await?;
+
+    match action.unwrap_or("run") {
+        "run" => run_migrations(database.pool()).await?,
+        "status" => {
+            for migration in migration_status(database.pool()).await? {
+                let state = if migration.applied { "applied" } else { "pending" };
+                println!("{:<8} {} {}", state, migration.version, migration.description);
+            }
+        }
+        other => anyhow::bail!("Unknown migrate action '{}', expected 'run' or 'status'", other),
+    }
+
+    database.close().await;
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .compact()
-        .init();
+    // Initialize PHI-redacting structured logging
+    HimsLogger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        return migrate_command(args.get(1).map(String::as_str)).await;
+    }
 
     tracing::info!("🏥 HIMS Core API Server starting...");
 
@@ -65,7 +127,8 @@ async fn main() -> Result<()> {
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
     tracing::info!("🚀 Server listening on http://0.0.0.0:3000");
 
-    axum::serve(listener, app).await?;
+    // Peer addresses identify clients to the rate limiter
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -9,7 +9,6 @@ use hims_core_sdk::HimsCore;
 use serde_json::{json, Value};
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing_subscriber;
 
 // Health check endpoint
 async fn health() -> Json<Value> {
@@ -182,6 +181,7 @@ async fn main() {
         enable_logging: true,
         country_code: Some("US".to_string()),
         state_code: Some("CA".to_string()),
+        tenant: None,
     };
     let _hims_core = HimsCore::new(config);
 
@@ -0,0 +1,249 @@
+//! Appointment booking and rescheduling
+//!
+//! Service types and reasons are sent as text; participants are references
+//! such as `Patient/<id>` or `Practitioner/<id>` with FHIR participation
+//! codes, such as `accepted` or `needs-action`.
+
+use chrono::{DateTime, Utc};
+use reqwest::{header, Method};
+use serde::Deserialize;
+
+use crate::client::connection::{fhir_code, if_match, resource_id, Bundle, HimsClient, SearchParameter};
+use crate::models::{
+    AppointmentParticipant, AppointmentStatus, CodeableConcept, ParticipantRequired, ParticipationStatus, Reference,
+    ResourceMeta,
+};
+use crate::modules::appointment::appointment_controller::AppointmentCreateRequest;
+use crate::HimsError;
+
+/// Someone taking part in an appointment
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppointmentAttendee {
+    /// Reference such as `Patient/<id>`
+    pub reference: String,
+    pub display: Option<String>,
+    pub required: bool,
+    /// `accepted`, `declined`, `tentative` or `needs-action`
+    pub status: String,
+}
+
+/// An appointment to book or reschedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppointmentInput {
+    pub service_types: Vec<String>,
+    pub reasons: Vec<String>,
+    pub description: Option<String>,
+    /// RFC 3339
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub minutes_duration: Option<u32>,
+    pub priority: Option<u32>,
+    pub attendees: Vec<AppointmentAttendee>,
+}
+
+/// A booked appointment
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppointmentRecord {
+    pub id: String,
+    pub version_id: Option<String>,
+    pub last_updated: String,
+    /// FHIR appointment status, such as `booked` or `cancelled`
+    pub status: String,
+    pub service_types: Vec<String>,
+    pub reasons: Vec<String>,
+    pub description: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub minutes_duration: Option<u32>,
+    pub priority: Option<u32>,
+    pub attendees: Vec<AppointmentAttendee>,
+}
+
+/// A page of appointments matching a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppointmentPage {
+    pub appointments: Vec<AppointmentRecord>,
+    pub total: Option<i64>,
+    pub next_cursor: Option<String>,
+}
+
+/// Appointment resource as the server returns it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppointmentResource {
+    #[serde(default)]
+    resource_type: String,
+    id: String,
+    meta: ResourceMeta,
+    status: AppointmentStatus,
+    #[serde(default)]
+    service_type: Vec<CodeableConcept>,
+    #[serde(default)]
+    reason_code: Vec<CodeableConcept>,
+    description: Option<String>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    minutes_duration: Option<u32>,
+    priority: Option<u32>,
+    #[serde(default)]
+    participant: Vec<AppointmentParticipant>,
+}
+
+fn texts(concepts: &[CodeableConcept]) -> Vec<String> {
+    concepts.iter().filter_map(CodeableConcept::display_text).collect()
+}
+
+fn concepts(texts: &[String]) -> Vec<CodeableConcept> {
+    texts
+        .iter()
+        .map(|text| CodeableConcept {
+            coding: Vec::new(),
+            text: Some(text.clone()),
+        })
+        .collect()
+}
+
+impl From<AppointmentResource> for AppointmentRecord {
+    fn from(appointment: AppointmentResource) -> Self {
+        AppointmentRecord {
+            id: appointment.id,
+            version_id: appointment.meta.version_id,
+            last_updated: appointment.meta.last_updated.to_rfc3339(),
+            status: appointment.status.to_string(),
+            service_types: texts(&appointment.service_type),
+            reasons: texts(&appointment.reason_code),
+            description: appointment.description,
+            start: appointment.start.map(|start| start.to_rfc3339()),
+            end: appointment.end.map(|end| end.to_rfc3339()),
+            minutes_duration: appointment.minutes_duration,
+            priority: appointment.priority,
+            attendees: appointment
+                .participant
+                .into_iter()
+                .filter_map(|participant| {
+                    let actor = participant.actor?;
+                    Some(AppointmentAttendee {
+                        reference: actor.reference,
+                        display: actor.display,
+                        required: !matches!(participant.required, Some(ParticipantRequired::Optional)),
+                        status: fhir_code(&participant.status),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl AppointmentInput {
+    /// The server's create and update request for this appointment
+    fn to_request(&self) -> Result<AppointmentCreateRequest, HimsError> {
+        let time = |field: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| {
+                    DateTime::parse_from_rfc3339(value).map(|time| time.with_timezone(&Utc)).map_err(|_| {
+                        HimsError::ValidationError {
+                            field: field.to_string(),
+                            message: format!("Not an RFC 3339 time: {}", value),
+                        }
+                    })
+                })
+                .transpose()
+        };
+        let participant = self
+            .attendees
+            .iter()
+            .map(|attendee| {
+                let status: ParticipationStatus =
+                    serde_json::from_value(serde_json::Value::String(attendee.status.clone())).map_err(|_| {
+                        HimsError::ValidationError {
+                            field: "attendees".to_string(),
+                            message: format!("Unknown participation status: {}", attendee.status),
+                        }
+                    })?;
+                Ok(AppointmentParticipant {
+                    actor: Some(Reference {
+                        reference: attendee.reference.clone(),
+                        display: attendee.display.clone(),
+                    }),
+                    required: Some(if attendee.required {
+                        ParticipantRequired::Required
+                    } else {
+                        ParticipantRequired::Optional
+                    }),
+                    status,
+                })
+            })
+            .collect::<Result<Vec<_>, HimsError>>()?;
+
+        Ok(AppointmentCreateRequest {
+            service_category: Vec::new(),
+            service_type: concepts(&self.service_types),
+            specialty: Vec::new(),
+            appointment_type: None,
+            reason_code: concepts(&self.reasons),
+            priority: self.priority,
+            description: self.description.clone(),
+            start: time("start", &self.start)?,
+            end: time("end", &self.end)?,
+            minutes_duration: self.minutes_duration,
+            participant,
+        })
+    }
+}
+
+impl HimsClient {
+    /// Book an appointment
+    pub async fn create_appointment(&self, appointment: AppointmentInput) -> Result<AppointmentRecord, HimsError> {
+        let request = self.request(Method::POST, "/api/v1/appointments").json(&appointment.to_request()?);
+        Ok(self.send_json::<AppointmentResource>(request).await?.into())
+    }
+
+    /// Get an appointment by ID
+    pub async fn get_appointment(&self, id: String) -> Result<AppointmentRecord, HimsError> {
+        let path = format!("/api/v1/appointments/{}", resource_id("id", &id)?);
+        Ok(self.send_json::<AppointmentResource>(self.request(Method::GET, &path)).await?.into())
+    }
+
+    /// Search appointments with FHIR search parameters, such as `patient`,
+    /// `date` or `status`
+    pub async fn search_appointments(&self, params: Vec<SearchParameter>) -> Result<AppointmentPage, HimsError> {
+        let path = format!("/api/v1/appointments{}", Self::search_query(&params)?);
+        let bundle: Bundle<serde_json::Value> = self.send_json(self.request(Method::GET, &path)).await?;
+        let next_cursor = bundle.next_cursor();
+        let appointments = bundle
+            .entry
+            .into_iter()
+            .filter_map(|entry| serde_json::from_value::<AppointmentResource>(entry.resource).ok())
+            .filter(|appointment| appointment.resource_type == "Appointment")
+            .map(AppointmentRecord::from)
+            .collect();
+        Ok(AppointmentPage {
+            appointments,
+            total: bundle.total,
+            next_cursor,
+        })
+    }
+
+    /// Reschedule or otherwise change an appointment, from the version it
+    /// was read at
+    pub async fn update_appointment(
+        &self,
+        id: String,
+        version_id: String,
+        appointment: AppointmentInput,
+    ) -> Result<AppointmentRecord, HimsError> {
+        let path = format!("/api/v1/appointments/{}", resource_id("id", &id)?);
+        let request = self
+            .request(Method::PUT, &path)
+            .header(header::IF_MATCH, if_match(&version_id))
+            .json(&appointment.to_request()?);
+        Ok(self.send_json::<AppointmentResource>(request).await?.into())
+    }
+
+    /// Delete an appointment
+    pub async fn delete_appointment(&self, id: String) -> Result<(), HimsError> {
+        let path = format!("/api/v1/appointments/{}", resource_id("id", &id)?);
+        self.send(self.request(Method::DELETE, &path)).await.map(|_| ())
+    }
+}
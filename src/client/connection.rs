@@ -0,0 +1,306 @@
+//! Connection to the HIMS server
+//!
+//! Holds the API endpoint, tenant and session tokens, and runs requests on
+//! a runtime of its own so the bindings' futures can be polled by the app's
+//! executor. Failed requests come back as `HimsError` variants by status.
+
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::{HimsConfig, HimsError};
+
+/// How long a request may take before it fails as a network error
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Header the tenant is named in before a token carries it
+pub(crate) const TENANT_HEADER: &str = "x-tenant-id";
+/// Header declaring why patient data is being accessed
+pub(crate) const PURPOSE_OF_USE_HEADER: &str = "x-purpose-of-use";
+
+/// A name and value of a FHIR search, such as `family=Smith` or `_count=20`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchParameter {
+    pub name: String,
+    pub value: String,
+}
+
+/// Error body the server's controllers reply with
+#[derive(Debug, Default, Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// `Bundle.link` of a search page
+#[derive(Debug, Deserialize)]
+pub(crate) struct BundleLink {
+    pub relation: String,
+    pub url: String,
+}
+
+/// A page of search results as the server bundles them
+#[derive(Debug, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+pub(crate) struct Bundle<T> {
+    pub total: Option<i64>,
+    #[serde(default)]
+    pub link: Vec<BundleLink>,
+    #[serde(default)]
+    pub entry: Vec<BundleEntry<T>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct BundleEntry<T> {
+    pub resource: T,
+}
+
+impl<T> Bundle<T> {
+    /// `_cursor` of the next page, to search again with
+    pub fn next_cursor(&self) -> Option<String> {
+        let next = self.link.iter().find(|link| link.relation == "next")?;
+        let (_, query) = next.url.split_once('?')?;
+        serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+            .ok()?
+            .into_iter()
+            .find(|(name, _)| name == "_cursor")
+            .map(|(_, cursor)| cursor)
+    }
+}
+
+/// Error for a failed request, by HTTP status
+pub(crate) fn status_error(status: StatusCode, body: &[u8]) -> HimsError {
+    let body: ErrorBody = serde_json::from_slice(body).unwrap_or_default();
+    let message = match (body.error.is_empty(), body.message.is_empty()) {
+        (_, false) => body.message,
+        (false, true) => body.error.clone(),
+        (true, true) => status.canonical_reason().unwrap_or("Request failed").to_string(),
+    };
+    match status {
+        StatusCode::UNAUTHORIZED => HimsError::AuthenticationError { message },
+        StatusCode::FORBIDDEN => HimsError::PermissionDenied { message },
+        StatusCode::NOT_FOUND | StatusCode::GONE => HimsError::NotFound { message },
+        StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED => HimsError::ConflictError { message },
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => HimsError::ValidationError {
+            field: body.error,
+            message,
+        },
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => {
+            HimsError::NetworkError { message }
+        }
+        status => HimsError::InternalError {
+            message: format!("{} ({})", message, status.as_u16()),
+        },
+    }
+}
+
+/// Parse an ID the server issued, before it is put in a URL
+pub(crate) fn resource_id(field: &str, id: &str) -> Result<String, HimsError> {
+    uuid::Uuid::parse_str(id.trim()).map(|id| id.to_string()).map_err(|_| HimsError::ValidationError {
+        field: field.to_string(),
+        message: format!("Not a valid ID: {}", id),
+    })
+}
+
+/// Client for the HIMS REST API, exported to the mobile app
+pub struct HimsClient {
+    pub(crate) base_url: String,
+    pub(crate) tenant: Option<String>,
+    http: reqwest::Client,
+    /// Without an overall timeout, for event streams held open
+    pub(crate) stream_http: reqwest::Client,
+    pub(crate) runtime: tokio::runtime::Runtime,
+    pub(crate) access_token: Arc<RwLock<Option<String>>>,
+    pub(crate) refresh_token: RwLock<Option<String>>,
+    /// Purpose of use declared with every request, such as `treatment`
+    pub(crate) purpose_of_use: RwLock<Option<String>>,
+    /// Event listener tasks, by listener ID
+    pub(crate) listeners: Mutex<HashMap<u64, JoinHandle<()>>>,
+    pub(crate) next_listener_id: AtomicU64,
+}
+
+impl HimsClient {
+    pub fn new(config: HimsConfig) -> Result<Self, HimsError> {
+        let base_url = config.api_endpoint.trim().trim_end_matches('/').to_string();
+        if !(base_url.starts_with("https://") || base_url.starts_with("http://")) {
+            return Err(HimsError::ConfigurationError {
+                message: format!("API endpoint must be an http(s) URL: {}", config.api_endpoint),
+            });
+        }
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| HimsError::ConfigurationError { message: e.to_string() })?;
+        let stream_http = reqwest::Client::builder()
+            .connect_timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| HimsError::ConfigurationError { message: e.to_string() })?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("hims-client")
+            .enable_all()
+            .build()
+            .map_err(|e| HimsError::InternalError { message: e.to_string() })?;
+        Ok(Self {
+            base_url,
+            tenant: config.tenant.filter(|tenant| !tenant.is_empty()),
+            http,
+            stream_http,
+            runtime,
+            access_token: Arc::new(RwLock::new(config.auth_token.filter(|token| !token.is_empty()))),
+            refresh_token: RwLock::new(None),
+            purpose_of_use: RwLock::new(None),
+            listeners: Mutex::new(HashMap::new()),
+            next_listener_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Declare why the app is accessing patient data, such as `treatment`
+    /// or `payment`; the server denies patient data without a purpose.
+    /// None stops declaring one.
+    pub fn set_purpose_of_use(&self, purpose_of_use: Option<String>) {
+        if let Ok(mut purpose) = self.purpose_of_use.write() {
+            *purpose = purpose_of_use.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+        }
+    }
+
+    /// A request to `path` under the API endpoint, with the tenant, the
+    /// session's access token and the declared purpose of use
+    pub(crate) fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(tenant) = &self.tenant {
+            request = request.header(TENANT_HEADER, tenant);
+        }
+        if let Some(token) = self.access_token.read().ok().and_then(|token| token.clone()) {
+            request = request.bearer_auth(token);
+        }
+        if let Some(purpose) = self.purpose_of_use.read().ok().and_then(|purpose| purpose.clone()) {
+            request = request.header(PURPOSE_OF_USE_HEADER, purpose);
+        }
+        request
+    }
+
+    /// Send a request on the client's runtime, returning the body of a
+    /// successful response
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<Vec<u8>, HimsError> {
+        self.runtime
+            .spawn(async move {
+                let response = request.send().await.map_err(|e| HimsError::NetworkError {
+                    message: e.to_string(),
+                })?;
+                let status = response.status();
+                let body = response.bytes().await.map_err(|e| HimsError::NetworkError {
+                    message: e.to_string(),
+                })?;
+                if status.is_success() {
+                    Ok(body.to_vec())
+                } else {
+                    Err(status_error(status, &body))
+                }
+            })
+            .await
+            .map_err(|e| HimsError::InternalError { message: e.to_string() })?
+    }
+
+    /// Send a request and read its JSON response
+    pub(crate) async fn send_json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, HimsError> {
+        let body = self.send(request).await?;
+        serde_json::from_slice(&body).map_err(|e| HimsError::InternalError {
+            message: format!("Unexpected response from server: {}", e),
+        })
+    }
+
+    /// A search's parameters as a query string
+    pub(crate) fn search_query(params: &[SearchParameter]) -> Result<String, HimsError> {
+        let pairs: Vec<(&str, &str)> = params.iter().map(|p| (p.name.as_str(), p.value.as_str())).collect();
+        serde_urlencoded::to_string(pairs)
+            .map(|query| if query.is_empty() { query } else { format!("?{}", query) })
+            .map_err(|e| HimsError::ValidationError {
+                field: "params".to_string(),
+                message: e.to_string(),
+            })
+    }
+}
+
+/// FHIR code a coded value serializes to, such as `female`
+pub(crate) fn fhir_code<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// `If-Match` value for a version
+pub(crate) fn if_match(version_id: &str) -> String {
+    format!("W/\"{}\"", version_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_errors() {
+        let body = br#"{"error":"Forbidden","message":"Missing required permission: read"}"#;
+        assert!(matches!(
+            status_error(StatusCode::FORBIDDEN, body),
+            HimsError::PermissionDenied { message } if message == "Missing required permission: read"
+        ));
+        assert!(matches!(
+            status_error(StatusCode::PRECONDITION_FAILED, b""),
+            HimsError::ConflictError { message } if message == "Precondition Failed"
+        ));
+        assert!(matches!(
+            status_error(StatusCode::BAD_REQUEST, br#"{"error":"birth_date","message":"in the future"}"#),
+            HimsError::ValidationError { field, .. } if field == "birth_date"
+        ));
+        assert!(matches!(status_error(StatusCode::SERVICE_UNAVAILABLE, b"down"), HimsError::NetworkError { .. }));
+    }
+
+    #[test]
+    fn test_next_cursor() {
+        let bundle: Bundle<serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "total": 40,
+            "link": [
+                { "relation": "self", "url": "/api/v1/patients?family=Rao" },
+                { "relation": "next", "url": "/api/v1/patients?family=Rao&_cursor=eyJrIjoxfQ" }
+            ],
+            "entry": []
+        }))
+        .unwrap();
+        assert_eq!(bundle.next_cursor().as_deref(), Some("eyJrIjoxfQ"));
+
+        let params = vec![
+            SearchParameter { name: "family".to_string(), value: "Rao Singh".to_string() },
+            SearchParameter { name: "_count".to_string(), value: "20".to_string() },
+        ];
+        assert_eq!(HimsClient::search_query(&params).unwrap(), "?family=Rao+Singh&_count=20");
+    }
+
+    #[test]
+    fn test_purpose_of_use_header() {
+        let client = HimsClient::new(HimsConfig {
+            api_endpoint: "http://localhost:8080".to_string(),
+            auth_token: None,
+            enable_logging: false,
+            country_code: None,
+            state_code: None,
+            tenant: None,
+        })
+        .unwrap();
+        let purpose = |client: &HimsClient| {
+            let request = client.request(Method::GET, "/api/v1/patients").build().unwrap();
+            request.headers().get(PURPOSE_OF_USE_HEADER).map(|value| value.to_str().unwrap().to_string())
+        };
+        assert_eq!(purpose(&client), None);
+
+        client.set_purpose_of_use(Some("treatment".to_string()));
+        assert_eq!(purpose(&client).as_deref(), Some("treatment"));
+
+        client.set_purpose_of_use(None);
+        assert_eq!(purpose(&client), None);
+    }
+}
@@ -0,0 +1,282 @@
+//! Push updates: the server's domain events delivered to app callbacks
+//!
+//! Each listener holds a server-sent events stream of `/api/v1/events/stream`
+//! on the client's runtime and reconnects with backoff when it drops.
+//! Events published while a listener is disconnected are not replayed, so
+//! listeners are told when that happens and should refetch what they show.
+
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use crate::client::connection::{status_error, HimsClient, SearchParameter, TENANT_HEADER};
+use crate::HimsError;
+
+/// Longest wait between reconnection attempts
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+/// Silence after which the stream is taken as dead; the server sends a
+/// keep-alive comment every 15 seconds
+const IDLE_TIMEOUT: Duration = Duration::from_secs(45);
+/// SSE event the server sends when the stream fell behind
+const LAGGED_EVENT: &str = "lagged";
+
+/// A domain event of the logged-in user's tenant
+#[derive(Debug, Clone, PartialEq)]
+pub struct HimsEvent {
+    pub id: String,
+    /// Subject such as `appointment.status_changed` or `result.received`
+    pub subject: String,
+    /// Event name such as `AppointmentStatusChanged`
+    pub event_type: String,
+    /// RFC 3339
+    pub occurred_at: String,
+    /// User whose request caused the event, when known
+    pub actor: Option<String>,
+    /// The event's fields as a JSON object, such as
+    /// `{"appointment_id": "...", "status": "arrived"}`
+    pub payload: String,
+}
+
+/// Receives domain events, implemented by the app. Called on the client's
+/// own threads, so implementations hand work to the UI thread themselves.
+pub trait EventListener: Send + Sync {
+    fn on_event(&self, event: HimsEvent);
+
+    /// Events may have been missed, because the connection dropped or the
+    /// app read too slowly; `reason` says which
+    fn on_events_missed(&self, reason: String);
+}
+
+/// Event envelope as the server streams it
+#[derive(Debug, Deserialize)]
+struct EventEnvelope {
+    id: String,
+    occurred_at: String,
+    actor: Option<String>,
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(flatten)]
+    fields: Map<String, Value>,
+}
+
+/// A complete server-sent event
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SseMessage {
+    pub event: String,
+    pub data: String,
+}
+
+/// Splits a server-sent events stream into messages as chunks arrive
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Messages completed by a chunk; partial lines wait for the next one
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseMessage> {
+        self.buffer.extend_from_slice(chunk);
+        let mut messages = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    messages.push(SseMessage {
+                        event: std::mem::take(&mut self.event),
+                        data: std::mem::take(&mut self.data).join("\n"),
+                    });
+                }
+                self.event.clear();
+                continue;
+            }
+            // Lines starting with a colon are keep-alive comments
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = value.to_string(),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+        messages
+    }
+}
+
+impl HimsEvent {
+    fn from_message(message: &SseMessage) -> Option<Self> {
+        let envelope: EventEnvelope = serde_json::from_str(&message.data).ok()?;
+        let mut fields = envelope.fields;
+        fields.remove("tenant_id");
+        Some(HimsEvent {
+            id: envelope.id,
+            subject: message.event.clone(),
+            event_type: envelope.event_type,
+            occurred_at: envelope.occurred_at,
+            actor: envelope.actor,
+            payload: Value::Object(fields).to_string(),
+        })
+    }
+}
+
+impl HimsClient {
+    /// Deliver the tenant's domain events to `listener` until it is removed.
+    /// `subjects` are exact subjects, `<noun>.*` or `*`; none means every
+    /// event. Returns the ID to remove the listener with.
+    pub fn add_event_listener(
+        &self,
+        subjects: Vec<String>,
+        listener: Box<dyn EventListener>,
+    ) -> Result<u64, HimsError> {
+        let subjects: Vec<String> =
+            subjects.into_iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+        if subjects.iter().any(|subject| subject.contains(',')) {
+            return Err(HimsError::ValidationError {
+                field: "subjects".to_string(),
+                message: "Subjects cannot contain commas".to_string(),
+            });
+        }
+        let query = Self::search_query(&[SearchParameter {
+            name: "subjects".to_string(),
+            value: subjects.join(","),
+        }])?;
+        let stream = self.stream_request(&format!("/api/v1/events/stream{}", query));
+
+        let id = self.next_listener_id.fetch_add(1, Ordering::Relaxed);
+        let task = self.runtime.spawn(Self::listen(stream, listener));
+        if let Ok(mut listeners) = self.listeners.lock() {
+            listeners.insert(id, task);
+        }
+        Ok(id)
+    }
+
+    /// Stop delivering events to a listener
+    pub fn remove_event_listener(&self, listener_id: u64) {
+        if let Some(task) = self.listeners.lock().ok().and_then(|mut listeners| listeners.remove(&listener_id)) {
+            task.abort();
+        }
+    }
+
+    /// Hold the stream open, reconnecting with backoff, until the listener
+    /// is removed or the server rejects its subjects
+    async fn listen(stream: impl Fn() -> reqwest::RequestBuilder + Send + 'static, listener: Box<dyn EventListener>) {
+        let mut delay = Duration::from_secs(1);
+        let mut connected_before = false;
+        loop {
+            let reason = match Self::read_stream(stream(), listener.as_ref(), &mut connected_before).await {
+                Ok(()) => "Event stream closed by the server".to_string(),
+                Err(e @ HimsError::ValidationError { .. }) => {
+                    listener.on_events_missed(format!("Event stream rejected: {}", e));
+                    return;
+                }
+                Err(e) => e.to_string(),
+            };
+            if connected_before {
+                // Connected long enough to have delivered, so retry promptly
+                delay = Duration::from_secs(1);
+                connected_before = false;
+            }
+            listener.on_events_missed(reason);
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    async fn read_stream(
+        request: reqwest::RequestBuilder,
+        listener: &dyn EventListener,
+        connected: &mut bool,
+    ) -> Result<(), HimsError> {
+        let network = |e: reqwest::Error| HimsError::NetworkError { message: e.to_string() };
+        let mut response = request.send().await.map_err(network)?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.bytes().await.map_err(network)?;
+            return Err(status_error(status, &body));
+        }
+        *connected = true;
+
+        let mut parser = SseParser::default();
+        loop {
+            let chunk = tokio::time::timeout(IDLE_TIMEOUT, response.chunk())
+                .await
+                .map_err(|_| HimsError::NetworkError {
+                    message: "Event stream went quiet".to_string(),
+                })?
+                .map_err(network)?;
+            let Some(chunk) = chunk else {
+                return Ok(());
+            };
+            for message in parser.feed(&chunk) {
+                if message.event == LAGGED_EVENT {
+                    listener.on_events_missed(format!("Fell behind the server and missed {} events", message.data));
+                } else if let Some(event) = HimsEvent::from_message(&message) {
+                    listener.on_event(event);
+                }
+            }
+        }
+    }
+
+    /// Builds a long-lived request for `path` with the session's current
+    /// token each time it is called, so reconnections pick up refreshed ones
+    fn stream_request(&self, path: &str) -> impl Fn() -> reqwest::RequestBuilder + Send + 'static {
+        let url = format!("{}{}", self.base_url, path);
+        let http = self.stream_http.clone();
+        let tenant = self.tenant.clone();
+        let access_token = self.access_token.clone();
+        move || {
+            let mut request = http.request(Method::GET, &url).header(reqwest::header::ACCEPT, "text/event-stream");
+            if let Some(tenant) = &tenant {
+                request = request.header(TENANT_HEADER, tenant);
+            }
+            if let Some(token) = access_token.read().ok().and_then(|token| token.clone()) {
+                request = request.bearer_auth(token);
+            }
+            request
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parsing() {
+        let mut parser = SseParser::default();
+        let data = serde_json::json!({
+            "id": "7d1c",
+            "tenant_id": "00000000-0000-0000-0000-000000000001",
+            "occurred_at": "2024-01-01T00:00:00Z",
+            "actor": null,
+            "type": "AppointmentStatusChanged",
+            "appointment_id": "a1",
+            "status": "arrived"
+        });
+
+        // Split mid-line, with a keep-alive comment in between
+        let stream = format!(
+            ":\n\nevent: appointment.status_changed\nid: 7d1c\ndata: {}\n\nevent: lagged\ndata: 3\n\n",
+            data
+        );
+        let (first, second) = stream.as_bytes().split_at(60);
+        let mut messages = parser.feed(first);
+        assert!(messages.is_empty());
+        messages.extend(parser.feed(second));
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1], SseMessage { event: "lagged".to_string(), data: "3".to_string() });
+
+        let event = HimsEvent::from_message(&messages[0]).unwrap();
+        assert_eq!(event.subject, "appointment.status_changed");
+        assert_eq!(event.event_type, "AppointmentStatusChanged");
+        let payload: Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["status"], "arrived");
+        assert!(payload.get("tenant_id").is_none());
+    }
+}
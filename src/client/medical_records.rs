@@ -0,0 +1,186 @@
+//! Clinical notes: writing, finalizing and searching medical records
+//!
+//! Record types and statuses are FHIR codes, such as `progress-note` and
+//! `final`. Template values travel as JSON text.
+
+use chrono::{DateTime, Utc};
+use reqwest::{header, Method};
+use serde::Deserialize;
+
+use crate::client::connection::{fhir_code, if_match, resource_id, Bundle, HimsClient, SearchParameter};
+use crate::models::{DocumentStatus, MedicalRecordType, Reference, ResourceMeta};
+use crate::modules::medical_record::medical_record_controller::MedicalRecordCreateRequest;
+use crate::HimsError;
+
+/// A note to write for a patient
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicalRecordInput {
+    pub patient_id: String,
+    pub encounter_id: Option<String>,
+    /// Such as `progress-note`, `discharge-summary` or `consultation`
+    pub record_type: String,
+    /// Rendered from `structured_data` when empty and a template is named
+    pub content: String,
+    /// Reference such as `Practitioner/<id>`
+    pub author: String,
+    pub template_id: Option<String>,
+    /// The template's field values, as a JSON object
+    pub structured_data: Option<String>,
+}
+
+/// A patient's medical record
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicalRecordDocument {
+    pub id: String,
+    pub version_id: Option<String>,
+    pub patient_id: String,
+    pub encounter_id: Option<String>,
+    pub record_type: String,
+    /// `preliminary`, `final`, `amended` or `entered-in-error`
+    pub status: String,
+    pub authors: Vec<String>,
+    pub content: String,
+    pub template_id: Option<String>,
+    pub structured_data: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A page of medical records matching a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicalRecordPage {
+    pub records: Vec<MedicalRecordDocument>,
+    pub total: Option<i64>,
+    pub next_cursor: Option<String>,
+}
+
+/// Medical record as the server returns it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MedicalRecordResource {
+    id: String,
+    meta: ResourceMeta,
+    patient_id: String,
+    encounter_id: Option<String>,
+    record_type: MedicalRecordType,
+    status: DocumentStatus,
+    #[serde(default)]
+    author: Vec<Reference>,
+    #[serde(default)]
+    content: String,
+    template_id: Option<String>,
+    structured_data: Option<serde_json::Value>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<MedicalRecordResource> for MedicalRecordDocument {
+    fn from(record: MedicalRecordResource) -> Self {
+        MedicalRecordDocument {
+            id: record.id,
+            version_id: record.meta.version_id,
+            patient_id: record.patient_id,
+            encounter_id: record.encounter_id,
+            record_type: fhir_code(&record.record_type),
+            status: fhir_code(&record.status),
+            authors: record.author.into_iter().map(|author| author.reference).collect(),
+            content: record.content,
+            template_id: record.template_id,
+            structured_data: record.structured_data.map(|data| data.to_string()),
+            created_at: record.created_at.to_rfc3339(),
+            updated_at: record.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+impl MedicalRecordInput {
+    /// The server's create request for this note
+    fn to_request(&self) -> Result<MedicalRecordCreateRequest, HimsError> {
+        let invalid = |field: &str, message: String| HimsError::ValidationError {
+            field: field.to_string(),
+            message,
+        };
+        let uuid = |field: &str, id: &str| {
+            uuid::Uuid::parse_str(id).map_err(|_| invalid(field, format!("Not a valid ID: {}", id)))
+        };
+        Ok(MedicalRecordCreateRequest {
+            patient_id: uuid("patient_id", &self.patient_id)?,
+            encounter_id: self.encounter_id.as_deref().map(|id| uuid("encounter_id", id)).transpose()?,
+            record_type: serde_json::from_value(serde_json::Value::String(self.record_type.clone()))
+                .map_err(|_| invalid("record_type", format!("Unknown record type: {}", self.record_type)))?,
+            content: self.content.clone(),
+            author: Reference {
+                reference: self.author.clone(),
+                display: None,
+            },
+            template_id: self.template_id.clone(),
+            structured_data: self
+                .structured_data
+                .as_deref()
+                .map(|data| {
+                    serde_json::from_str::<serde_json::Value>(data)
+                        .ok()
+                        .filter(serde_json::Value::is_object)
+                        .ok_or_else(|| invalid("structured_data", "Must be a JSON object".to_string()))
+                })
+                .transpose()?,
+        })
+    }
+}
+
+impl HimsClient {
+    /// Write a medical record
+    pub async fn create_medical_record(&self, record: MedicalRecordInput) -> Result<MedicalRecordDocument, HimsError> {
+        let request = self.request(Method::POST, "/api/v1/medical-records").json(&record.to_request()?);
+        Ok(self.send_json::<MedicalRecordResource>(request).await?.into())
+    }
+
+    /// Get a medical record by ID
+    pub async fn get_medical_record(&self, id: String) -> Result<MedicalRecordDocument, HimsError> {
+        let path = format!("/api/v1/medical-records/{}", resource_id("id", &id)?);
+        Ok(self.send_json::<MedicalRecordResource>(self.request(Method::GET, &path)).await?.into())
+    }
+
+    /// Search medical records with search parameters, such as `patient` or
+    /// `type`
+    pub async fn search_medical_records(
+        &self,
+        params: Vec<SearchParameter>,
+    ) -> Result<MedicalRecordPage, HimsError> {
+        let path = format!("/api/v1/medical-records{}", Self::search_query(&params)?);
+        let bundle: Bundle<MedicalRecordResource> = self.send_json(self.request(Method::GET, &path)).await?;
+        let next_cursor = bundle.next_cursor();
+        Ok(MedicalRecordPage {
+            records: bundle.entry.into_iter().map(|entry| entry.resource.into()).collect(),
+            total: bundle.total,
+            next_cursor,
+        })
+    }
+
+    /// Replace a preliminary record's content, from the version it was read at
+    pub async fn update_medical_record(
+        &self,
+        id: String,
+        version_id: String,
+        content: String,
+    ) -> Result<MedicalRecordDocument, HimsError> {
+        let path = format!("/api/v1/medical-records/{}", resource_id("id", &id)?);
+        let request = self
+            .request(Method::PUT, &path)
+            .header(header::IF_MATCH, if_match(&version_id))
+            .json(&content);
+        Ok(self.send_json::<MedicalRecordResource>(request).await?.into())
+    }
+
+    /// Finalize a record, from the version it was read at; finalized records
+    /// can only be amended
+    pub async fn finalize_medical_record(
+        &self,
+        id: String,
+        version_id: String,
+    ) -> Result<MedicalRecordDocument, HimsError> {
+        let path = format!("/api/v1/medical-records/{}/finalize", resource_id("id", &id)?);
+        let request = self.request(Method::PUT, &path).header(header::IF_MATCH, if_match(&version_id));
+        Ok(self.send_json::<MedicalRecordResource>(request).await?.into())
+    }
+}
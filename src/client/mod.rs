@@ -0,0 +1,35 @@
+//! Client for the HIMS server
+//!
+//! Typed access to the REST API for the mobile app, which calls it through
+//! the uniffi bindings as async methods on `HimsClient`:
+//! - Login, token refresh, logout and permission checks
+//! - Patient registration, lookup, search and update
+//! - Appointment booking, search and rescheduling
+//! - Writing, finalizing and searching medical records
+//! - Push updates of the tenant's domain events to app listeners
+//!
+//! Updates are sent with the version they were read at, so a conflicting
+//! edit fails with `HimsError::ConflictError` instead of overwriting it.
+//!
+//! The bindings go through the server rather than wrapping the module
+//! services in-process. The services run against the server's PostgreSQL
+//! pool, and every read and change is authorized by its engine and audited
+//! there; services linked into the app would need a database connection
+//! from the device and would take those decisions on it, where a modified
+//! app could skip them. The app still calls typed async methods that fail
+//! with `HimsError` variants, not raw HTTP. Working offline without the
+//! server is left to the desktop app's local commands.
+
+pub mod appointments;
+pub mod connection;
+pub mod events;
+pub mod medical_records;
+pub mod patients;
+pub mod session;
+
+pub use appointments::*;
+pub use connection::{HimsClient, SearchParameter};
+pub use events::{EventListener, HimsEvent};
+pub use medical_records::*;
+pub use patients::*;
+pub use session::*;
@@ -0,0 +1,318 @@
+//! Patient registration, lookup and search
+//!
+//! Patients are flattened to their official name, first phone and email,
+//! and first address, which is what the app's registration screens edit.
+//! Coded values are FHIR codes, such as `female` for gender.
+
+use reqwest::{header, Method};
+use serde::Deserialize;
+
+use crate::client::connection::{fhir_code, if_match, resource_id, Bundle, HimsClient, SearchParameter};
+use crate::models::{Address, ContactPoint, ContactPointSystem, Gender, HumanName, Identifier, ResourceMeta};
+use crate::modules::patient::patient_controller::PatientCreateRequest;
+use crate::HimsError;
+
+/// An identifier of a patient, such as an MRN or ABHA number
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatientIdentifier {
+    pub system: Option<String>,
+    pub value: String,
+}
+
+/// Demographics a patient is registered or updated with
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatientInput {
+    pub identifiers: Vec<PatientIdentifier>,
+    pub family_name: Option<String>,
+    pub given_names: Vec<String>,
+    /// `male`, `female`, `other` or `unknown`
+    pub gender: String,
+    /// YYYY-MM-DD
+    pub birth_date: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address_lines: Vec<String>,
+    pub city: Option<String>,
+    pub district: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
+
+/// A registered patient
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatientRecord {
+    pub id: String,
+    /// Version to update the patient from
+    pub version_id: Option<String>,
+    /// RFC 3339
+    pub last_updated: String,
+    pub active: bool,
+    pub identifiers: Vec<PatientIdentifier>,
+    pub family_name: Option<String>,
+    pub given_names: Vec<String>,
+    pub gender: String,
+    pub birth_date: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address_lines: Vec<String>,
+    pub city: Option<String>,
+    pub district: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
+
+/// A page of patients matching a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatientPage {
+    pub patients: Vec<PatientRecord>,
+    /// Matches across all pages, unless counting was turned off
+    pub total: Option<i64>,
+    /// `_cursor` to search again with for the next page
+    pub next_cursor: Option<String>,
+}
+
+/// Patient resource as the server returns it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PatientResource {
+    #[serde(default)]
+    resource_type: String,
+    id: String,
+    meta: ResourceMeta,
+    #[serde(default)]
+    identifier: Vec<Identifier>,
+    #[serde(default = "default_active")]
+    active: bool,
+    #[serde(default)]
+    name: Vec<HumanName>,
+    #[serde(default)]
+    telecom: Vec<ContactPoint>,
+    gender: Gender,
+    birth_date: Option<String>,
+    #[serde(default)]
+    address: Vec<Address>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl From<PatientResource> for PatientRecord {
+    fn from(patient: PatientResource) -> Self {
+        let name = patient.name.into_iter().next();
+        let address = patient.address.into_iter().next();
+        let contact = |system: &str| {
+            patient.telecom.iter().find(|point| fhir_code(&point.system) == system).map(|point| point.value.clone())
+        };
+        PatientRecord {
+            phone: contact("phone"),
+            email: contact("email"),
+            id: patient.id,
+            version_id: patient.meta.version_id,
+            last_updated: patient.meta.last_updated.to_rfc3339(),
+            active: patient.active,
+            identifiers: patient
+                .identifier
+                .into_iter()
+                .map(|identifier| PatientIdentifier {
+                    system: identifier.system,
+                    value: identifier.value,
+                })
+                .collect(),
+            family_name: name.as_ref().and_then(|name| name.family.clone()),
+            given_names: name.map(|name| name.given).unwrap_or_default(),
+            gender: fhir_code(&patient.gender),
+            birth_date: patient.birth_date,
+            address_lines: address.as_ref().map(|a| a.line.clone()).unwrap_or_default(),
+            city: address.as_ref().and_then(|a| a.city.clone()),
+            district: address.as_ref().and_then(|a| a.district.clone()),
+            state: address.as_ref().and_then(|a| a.state.clone()),
+            postal_code: address.as_ref().and_then(|a| a.postal_code.clone()),
+            country: address.and_then(|a| a.country),
+        }
+    }
+}
+
+impl PatientInput {
+    /// The server's create and update request for these demographics
+    fn to_request(&self) -> Result<PatientCreateRequest, HimsError> {
+        let invalid = |field: &str, message: String| HimsError::ValidationError {
+            field: field.to_string(),
+            message,
+        };
+        let gender: Gender = serde_json::from_value(serde_json::Value::String(self.gender.clone()))
+            .map_err(|_| invalid("gender", format!("Unknown gender: {}", self.gender)))?;
+        let birth_date = self
+            .birth_date
+            .as_deref()
+            .map(|date| {
+                chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| invalid("birth_date", format!("Not a YYYY-MM-DD date: {}", date)))
+            })
+            .transpose()?;
+        if self.family_name.is_none() && self.given_names.is_empty() {
+            return Err(invalid("name", "A family or given name is required".to_string()));
+        }
+
+        let contact = |system: ContactPointSystem, value: &Option<String>| {
+            value.clone().filter(|value| !value.is_empty()).map(|value| ContactPoint {
+                system,
+                value,
+                use_type: None,
+                rank: None,
+            })
+        };
+        let has_address = !self.address_lines.is_empty()
+            || [&self.city, &self.district, &self.state, &self.postal_code, &self.country]
+                .iter()
+                .any(|part| part.is_some());
+
+        Ok(PatientCreateRequest {
+            identifier: self
+                .identifiers
+                .iter()
+                .map(|identifier| Identifier {
+                    use_type: None,
+                    system: identifier.system.clone(),
+                    value: identifier.value.clone(),
+                })
+                .collect(),
+            name: vec![HumanName {
+                use_type: None,
+                text: None,
+                family: self.family_name.clone(),
+                given: self.given_names.clone(),
+                prefix: Vec::new(),
+                suffix: Vec::new(),
+            }],
+            telecom: [contact(ContactPointSystem::Phone, &self.phone), contact(ContactPointSystem::Email, &self.email)]
+                .into_iter()
+                .flatten()
+                .collect(),
+            gender,
+            birth_date,
+            address: has_address
+                .then(|| Address {
+                    use_type: None,
+                    address_type: None,
+                    text: None,
+                    line: self.address_lines.clone(),
+                    city: self.city.clone(),
+                    district: self.district.clone(),
+                    state: self.state.clone(),
+                    postal_code: self.postal_code.clone(),
+                    country: self.country.clone(),
+                })
+                .into_iter()
+                .collect(),
+            marital_status: None,
+            contact: Vec::new(),
+            communication: Vec::new(),
+        })
+    }
+}
+
+impl HimsClient {
+    /// Register a patient
+    pub async fn create_patient(&self, patient: PatientInput) -> Result<PatientRecord, HimsError> {
+        let request = self.request(Method::POST, "/api/v1/patients").json(&patient.to_request()?);
+        Ok(self.send_json::<PatientResource>(request).await?.into())
+    }
+
+    /// Get a patient by ID
+    pub async fn get_patient(&self, id: String) -> Result<PatientRecord, HimsError> {
+        let path = format!("/api/v1/patients/{}", resource_id("id", &id)?);
+        Ok(self.send_json::<PatientResource>(self.request(Method::GET, &path)).await?.into())
+    }
+
+    /// Search patients with FHIR search parameters, such as `family`,
+    /// `birthdate` or `identifier`
+    pub async fn search_patients(&self, params: Vec<SearchParameter>) -> Result<PatientPage, HimsError> {
+        let path = format!("/api/v1/patients{}", Self::search_query(&params)?);
+        let bundle: Bundle<serde_json::Value> = self.send_json(self.request(Method::GET, &path)).await?;
+        let next_cursor = bundle.next_cursor();
+        let patients = bundle
+            .entry
+            .into_iter()
+            .filter_map(|entry| serde_json::from_value::<PatientResource>(entry.resource).ok())
+            .filter(|patient| patient.resource_type == "Patient")
+            .map(PatientRecord::from)
+            .collect();
+        Ok(PatientPage {
+            patients,
+            total: bundle.total,
+            next_cursor,
+        })
+    }
+
+    /// Update a patient's demographics, from the version they were read at
+    pub async fn update_patient(
+        &self,
+        id: String,
+        version_id: String,
+        patient: PatientInput,
+    ) -> Result<PatientRecord, HimsError> {
+        let path = format!("/api/v1/patients/{}", resource_id("id", &id)?);
+        let request = self
+            .request(Method::PUT, &path)
+            .header(header::IF_MATCH, if_match(&version_id))
+            .json(&patient.to_request()?);
+        Ok(self.send_json::<PatientResource>(request).await?.into())
+    }
+
+    /// Deactivate a patient
+    pub async fn delete_patient(&self, id: String) -> Result<(), HimsError> {
+        let path = format!("/api/v1/patients/{}", resource_id("id", &id)?);
+        self.send(self.request(Method::DELETE, &path)).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patient_round_trip() {
+        let input = PatientInput {
+            identifiers: vec![PatientIdentifier {
+                system: Some("https://healthid.ndhm.gov.in".to_string()),
+                value: "91-1234-5678-9012".to_string(),
+            }],
+            family_name: Some("Rao".to_string()),
+            given_names: vec!["Meera".to_string()],
+            gender: "female".to_string(),
+            birth_date: Some("1990-04-12".to_string()),
+            phone: Some("+919800000000".to_string()),
+            email: None,
+            address_lines: vec!["12 MG Road".to_string()],
+            city: Some("Bengaluru".to_string()),
+            district: None,
+            state: Some("KA".to_string()),
+            postal_code: Some("560001".to_string()),
+            country: Some("IN".to_string()),
+        };
+        let request = serde_json::to_value(input.to_request().unwrap()).unwrap();
+        assert_eq!(request["gender"], "female");
+        assert_eq!(request["telecom"].as_array().unwrap().len(), 1);
+
+        // The server echoes the request back as a resource with an ID and version
+        let mut resource = request.clone();
+        resource["resourceType"] = "Patient".into();
+        resource["id"] = "0b0e4f9a-6a53-4d55-9d6e-3b7a1c2d9f10".into();
+        resource["birthDate"] = request["birth_date"].clone();
+        resource["meta"] = serde_json::json!({
+            "version_id": "2", "last_updated": "2024-01-01T00:00:00Z", "profile": [], "security": [], "tag": []
+        });
+        let record = PatientRecord::from(serde_json::from_value::<PatientResource>(resource).unwrap());
+        assert_eq!(record.version_id.as_deref(), Some("2"));
+        assert_eq!(record.family_name, input.family_name);
+        assert_eq!(record.phone, input.phone);
+        assert_eq!(record.city, input.city);
+        assert_eq!(record.birth_date, input.birth_date);
+
+        let invalid = PatientInput { gender: "f".to_string(), ..input };
+        assert!(matches!(invalid.to_request(), Err(HimsError::ValidationError { field, .. }) if field == "gender"));
+    }
+}
@@ -0,0 +1,235 @@
+//! Login sessions and the permissions they carry
+//!
+//! Tokens from a login are kept by the client and sent with every later
+//! request; refreshing rotates them and logging out revokes both. Access to
+//! a particular resource is checked with the server's authorization engine.
+
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::client::connection::HimsClient;
+use crate::HimsError;
+
+/// The user a session is logged in as
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SessionUser {
+    #[serde(rename = "id")]
+    pub user_id: String,
+    pub username: String,
+    pub role: String,
+    /// Actions the user may take, such as `read` or `manage_users`
+    pub permissions: Vec<String>,
+}
+
+/// A logged-in session
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+    pub user: SessionUser,
+    pub session_id: String,
+    /// Seconds until the access token expires and should be refreshed
+    pub expires_in: u64,
+}
+
+/// The authorization engine's decision on an action
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AccessCheck {
+    pub allowed: bool,
+    /// `allow`, `deny`, `require_approval`, `require_mfa`,
+    /// `allow_with_restrictions`, `emergency_access` or `break_glass_access`
+    pub decision: String,
+    #[serde(default)]
+    pub reasons: Vec<String>,
+    /// What would allow a denied action, such as MFA
+    #[serde(default)]
+    pub requirements: Vec<String>,
+    #[serde(default)]
+    pub restrictions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginResponse {
+    user: SessionUser,
+    token: String,
+    refresh_token: String,
+    expires_in: u64,
+    session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenPair {
+    access_token: String,
+    refresh_token: String,
+    expires_in: i64,
+    session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenValidation {
+    valid: bool,
+    user: Option<SessionUser>,
+}
+
+impl HimsClient {
+    fn store_tokens(&self, access_token: Option<String>, refresh_token: Option<String>) {
+        if let Ok(mut token) = self.access_token.write() {
+            *token = access_token;
+        }
+        if let Ok(mut token) = self.refresh_token.write() {
+            *token = refresh_token;
+        }
+    }
+
+    fn tokens(&self) -> (Option<String>, Option<String>) {
+        (
+            self.access_token.read().ok().and_then(|token| token.clone()),
+            self.refresh_token.read().ok().and_then(|token| token.clone()),
+        )
+    }
+
+    /// Log in with a username and password
+    pub async fn login(&self, username: String, password: String) -> Result<SessionInfo, HimsError> {
+        let request = self
+            .request(Method::POST, "/api/v1/auth/login")
+            .json(&json!({ "username": username, "password": password }));
+        let response: LoginResponse = self.send_json(request).await?;
+        self.store_tokens(Some(response.token), Some(response.refresh_token));
+        Ok(SessionInfo {
+            user: response.user,
+            session_id: response.session_id,
+            expires_in: response.expires_in,
+        })
+    }
+
+    /// Exchange the refresh token for new tokens before the access token
+    /// expires
+    pub async fn refresh_session(&self) -> Result<SessionInfo, HimsError> {
+        let (_, Some(refresh_token)) = self.tokens() else {
+            return Err(HimsError::AuthenticationError {
+                message: "Not logged in".to_string(),
+            });
+        };
+        let request = self
+            .request(Method::POST, "/api/v1/auth/refresh")
+            .json(&json!({ "refresh_token": refresh_token }));
+        let tokens: TokenPair = self.send_json(request).await?;
+        self.store_tokens(Some(tokens.access_token), Some(tokens.refresh_token));
+        Ok(SessionInfo {
+            user: self.current_user().await?,
+            session_id: tokens.session_id,
+            expires_in: tokens.expires_in.max(0) as u64,
+        })
+    }
+
+    /// Revoke the session's tokens. They are forgotten even if the server
+    /// cannot be reached.
+    pub async fn logout(&self) -> Result<(), HimsError> {
+        let (access_token, refresh_token) = self.tokens();
+        if access_token.is_none() && refresh_token.is_none() {
+            return Ok(());
+        }
+        let request = self
+            .request(Method::POST, "/api/v1/auth/revoke")
+            .json(&json!({ "token": access_token, "refresh_token": refresh_token }));
+        self.store_tokens(None, None);
+        self.send(request).await.map(|_| ())
+    }
+
+    /// The user the session is logged in as, with their current permissions
+    pub async fn current_user(&self) -> Result<SessionUser, HimsError> {
+        let (Some(token), _) = self.tokens() else {
+            return Err(HimsError::AuthenticationError {
+                message: "Not logged in".to_string(),
+            });
+        };
+        let request = self.request(Method::POST, "/api/v1/auth/validate").json(&json!({ "token": token }));
+        let validation: TokenValidation = self.send_json(request).await?;
+        match validation.user {
+            Some(user) if validation.valid => Ok(user),
+            _ => Err(HimsError::AuthenticationError {
+                message: "Session has expired or was revoked".to_string(),
+            }),
+        }
+    }
+
+    /// Whether the logged-in user has a permission, such as `read` or
+    /// `manage_users`, as the server grants it now
+    pub async fn has_permission(&self, permission: String) -> Result<bool, HimsError> {
+        Ok(self.current_user().await?.permissions.contains(&permission))
+    }
+
+    /// Whether the logged-in user may take `action`, such as `read`, on
+    /// `resource`, named `type:id` as in `patient:<id>`, as the server's
+    /// authorization engine decides it now for the declared purpose of use.
+    /// Unlike `has_permission`, this weighs the user's relationship to the
+    /// patient, consent and confidentiality labels.
+    pub async fn check_access(&self, action: String, resource: String) -> Result<AccessCheck, HimsError> {
+        let request = self
+            .request(Method::POST, "/api/v1/authorization/check")
+            .json(&json!({ "action": action, "resource": resource }));
+        self.send_json(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HimsConfig;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_check_access() {
+        // The client runs requests on a runtime of its own, which must not be
+        // dropped inside another, so the mock server gets a separate one
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let patient = format!("patient:{}", uuid::Uuid::new_v4());
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/api/v1/authorization/check"))
+                .and(header("x-purpose-of-use", "treatment"))
+                .and(body_json(json!({ "action": "read", "resource": patient })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "allowed": true,
+                    "decision": "allow",
+                    "reasons": ["Primary physician of the patient"],
+                    "requirements": [],
+                    "restrictions": []
+                })))
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path("/api/v1/authorization/check"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "allowed": false,
+                    "decision": "deny",
+                    "reasons": ["Purpose of use required for patient data access"],
+                    "requirements": ["purpose_of_use"],
+                    "restrictions": []
+                })))
+                .mount(&server)
+                .await;
+            server
+        });
+        let client = HimsClient::new(HimsConfig {
+            api_endpoint: server.uri(),
+            auth_token: Some("access-token".to_string()),
+            enable_logging: false,
+            country_code: None,
+            state_code: None,
+            tenant: None,
+        })
+        .unwrap();
+
+        // Denied until a purpose of use is declared
+        let check = runtime.block_on(client.check_access("read".to_string(), patient.clone())).unwrap();
+        assert!(!check.allowed);
+        assert_eq!(check.requirements, vec!["purpose_of_use".to_string()]);
+
+        client.set_purpose_of_use(Some("treatment".to_string()));
+        let check = runtime.block_on(client.check_access("read".to_string(), patient.clone())).unwrap();
+        assert!(check.allowed);
+        assert_eq!(check.decision, "allow");
+    }
+}
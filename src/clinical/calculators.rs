@@ -0,0 +1,283 @@
+//! Bedside scores and equations
+//!
+//! BMI, eGFR (CKD-EPI 2021), Apgar and CHA₂DS₂-VASc. Inputs are validated
+//! and converted to each equation's units before it runs; results carry the
+//! interpretation band alongside the value.
+
+use crate::clinical::units::{to_canonical, Dimension, Measurement};
+use crate::HimsError;
+
+fn invalid(field: &str, message: impl Into<String>) -> HimsError {
+    HimsError::ValidationError { field: field.to_string(), message: message.into() }
+}
+
+/// Sex used by sex-specific equations and growth references
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sex {
+    Male,
+    Female,
+}
+
+/// WHO adult BMI classification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BmiCategory {
+    Underweight,
+    Normal,
+    Overweight,
+    ObeseClassI,
+    ObeseClassII,
+    ObeseClassIII,
+}
+
+impl BmiCategory {
+    pub fn from_bmi(bmi: f64) -> Self {
+        match bmi {
+            bmi if bmi < 18.5 => BmiCategory::Underweight,
+            bmi if bmi < 25.0 => BmiCategory::Normal,
+            bmi if bmi < 30.0 => BmiCategory::Overweight,
+            bmi if bmi < 35.0 => BmiCategory::ObeseClassI,
+            bmi if bmi < 40.0 => BmiCategory::ObeseClassII,
+            _ => BmiCategory::ObeseClassIII,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BmiResult {
+    /// kg/m2
+    pub bmi: Measurement,
+    /// Adult category; children are classified by BMI-for-age percentile
+    pub category: BmiCategory,
+}
+
+/// Body mass index from weight and height in any accepted UCUM units
+pub fn calculate_bmi(weight: Measurement, height: Measurement) -> Result<BmiResult, HimsError> {
+    let kg = to_canonical("weight", &weight, Dimension::Mass)?;
+    let metres = to_canonical("height", &height, Dimension::Length)? / 100.0;
+    let bmi = kg / (metres * metres);
+    Ok(BmiResult {
+        bmi: Measurement::new(bmi, Dimension::BodyMassIndex.canonical_unit()),
+        category: BmiCategory::from_bmi(bmi),
+    })
+}
+
+/// KDIGO GFR category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CkdStage {
+    /// ≥ 90, normal or high
+    G1,
+    /// 60-89, mildly decreased
+    G2,
+    /// 45-59, mildly to moderately decreased
+    G3a,
+    /// 30-44, moderately to severely decreased
+    G3b,
+    /// 15-29, severely decreased
+    G4,
+    /// < 15, kidney failure
+    G5,
+}
+
+impl CkdStage {
+    pub fn from_egfr(egfr: f64) -> Self {
+        match egfr {
+            egfr if egfr >= 90.0 => CkdStage::G1,
+            egfr if egfr >= 60.0 => CkdStage::G2,
+            egfr if egfr >= 45.0 => CkdStage::G3a,
+            egfr if egfr >= 30.0 => CkdStage::G3b,
+            egfr if egfr >= 15.0 => CkdStage::G4,
+            _ => CkdStage::G5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EgfrInput {
+    /// Serum creatinine (IDMS-traceable), in mg/dL, mg/L, umol/L or mmol/L
+    pub creatinine: Measurement,
+    pub age_years: u32,
+    pub sex: Sex,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EgfrResult {
+    /// mL/min/{1.73_m2}
+    pub egfr: Measurement,
+    pub stage: CkdStage,
+}
+
+/// UCUM code of eGFR normalised to body surface area
+pub const EGFR_UNIT: &str = "mL/min/{1.73_m2}";
+
+/// eGFR by the race-free CKD-EPI 2021 creatinine equation, for adults
+pub fn calculate_egfr(input: EgfrInput) -> Result<EgfrResult, HimsError> {
+    if input.age_years < 18 {
+        return Err(invalid("age_years", "CKD-EPI is validated for adults (18 years and over)"));
+    }
+    let creatinine = to_canonical("creatinine", &input.creatinine, Dimension::Creatinine)?;
+
+    let (kappa, alpha, sex_factor) = match input.sex {
+        Sex::Female => (0.7, -0.241, 1.012),
+        Sex::Male => (0.9, -0.302, 1.0),
+    };
+    let ratio = creatinine / kappa;
+    let egfr = 142.0
+        * ratio.min(1.0).powf(alpha)
+        * ratio.max(1.0).powf(-1.200)
+        * 0.9938_f64.powi(input.age_years as i32)
+        * sex_factor;
+
+    Ok(EgfrResult { egfr: Measurement::new(egfr, EGFR_UNIT), stage: CkdStage::from_egfr(egfr) })
+}
+
+/// The five Apgar signs, each scored 0, 1 or 2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApgarInput {
+    /// Skin colour: 0 blue or pale, 1 blue extremities, 2 pink
+    pub appearance: u8,
+    /// Heart rate: 0 absent, 1 below 100/min, 2 100/min or more
+    pub pulse: u8,
+    /// Reflex irritability: 0 none, 1 grimace, 2 cry or cough
+    pub grimace: u8,
+    /// Muscle tone: 0 limp, 1 some flexion, 2 active motion
+    pub activity: u8,
+    /// Breathing: 0 absent, 1 weak or irregular, 2 strong cry
+    pub respiration: u8,
+}
+
+/// AAP/ACOG interpretation of an Apgar total
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApgarInterpretation {
+    /// 7-10
+    Reassuring,
+    /// 4-6
+    ModeratelyAbnormal,
+    /// 0-3
+    Low,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApgarResult {
+    pub score: u8,
+    pub interpretation: ApgarInterpretation,
+}
+
+pub fn calculate_apgar(input: ApgarInput) -> Result<ApgarResult, HimsError> {
+    let signs = [
+        ("appearance", input.appearance),
+        ("pulse", input.pulse),
+        ("grimace", input.grimace),
+        ("activity", input.activity),
+        ("respiration", input.respiration),
+    ];
+    if let Some((field, value)) = signs.iter().find(|(_, value)| *value > 2) {
+        return Err(invalid(field, format!("Apgar signs are scored 0-2, not {}", value)));
+    }
+    let score: u8 = signs.iter().map(|(_, value)| value).sum();
+    let interpretation = match score {
+        7..=10 => ApgarInterpretation::Reassuring,
+        4..=6 => ApgarInterpretation::ModeratelyAbnormal,
+        _ => ApgarInterpretation::Low,
+    };
+    Ok(ApgarResult { score, interpretation })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cha2ds2VascInput {
+    pub age_years: u32,
+    pub sex: Sex,
+    pub congestive_heart_failure: bool,
+    pub hypertension: bool,
+    pub diabetes: bool,
+    /// Prior stroke, TIA or thromboembolism
+    pub stroke_or_tia: bool,
+    /// Prior myocardial infarction, peripheral artery disease or aortic plaque
+    pub vascular_disease: bool,
+}
+
+/// Oral anticoagulation advice for atrial fibrillation (ESC 2020), which
+/// discounts the point scored for female sex alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnticoagulationRecommendation {
+    NotRecommended,
+    Consider,
+    Recommended,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cha2ds2VascResult {
+    pub score: u8,
+    pub recommendation: AnticoagulationRecommendation,
+}
+
+pub fn calculate_cha2ds2_vasc(input: Cha2ds2VascInput) -> Result<Cha2ds2VascResult, HimsError> {
+    if input.age_years > 130 {
+        return Err(invalid("age_years", format!("{} is not a plausible age", input.age_years)));
+    }
+    let age_points = match input.age_years {
+        75.. => 2,
+        65..=74 => 1,
+        _ => 0,
+    };
+    let sex_points = u8::from(input.sex == Sex::Female);
+    let score = u8::from(input.congestive_heart_failure)
+        + u8::from(input.hypertension)
+        + age_points
+        + u8::from(input.diabetes)
+        + 2 * u8::from(input.stroke_or_tia)
+        + u8::from(input.vascular_disease)
+        + sex_points;
+
+    let recommendation = match score - sex_points {
+        0 => AnticoagulationRecommendation::NotRecommended,
+        1 => AnticoagulationRecommendation::Consider,
+        _ => AnticoagulationRecommendation::Recommended,
+    };
+    Ok(Cha2ds2VascResult { score, recommendation })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bmi_and_egfr() {
+        let bmi = calculate_bmi(Measurement::new(70.0, "kg"), Measurement::new(70.0, "[in_i]")).unwrap();
+        assert!((bmi.bmi.value - 22.14).abs() < 0.01);
+        assert_eq!(bmi.category, BmiCategory::Normal);
+
+        // 60-year-old woman with creatinine 1.0 mg/dL (88.42 µmol/L): 64.5
+        let input = EgfrInput { creatinine: Measurement::new(88.42, "umol/L"), age_years: 60, sex: Sex::Female };
+        let egfr = calculate_egfr(input.clone()).unwrap();
+        assert!((egfr.egfr.value - 64.5).abs() < 0.1);
+        assert_eq!(egfr.stage, CkdStage::G2);
+        assert!(calculate_egfr(EgfrInput { age_years: 12, ..input }).is_err());
+    }
+
+    #[test]
+    fn test_scores() {
+        let apgar = ApgarInput { appearance: 1, pulse: 2, grimace: 2, activity: 2, respiration: 2 };
+        assert_eq!(
+            calculate_apgar(apgar.clone()).unwrap(),
+            ApgarResult { score: 9, interpretation: ApgarInterpretation::Reassuring }
+        );
+        assert!(calculate_apgar(ApgarInput { pulse: 3, ..apgar }).is_err());
+
+        // A woman of 70 with hypertension: 3 points, 1 of them for sex
+        let cha2ds2_vasc = Cha2ds2VascInput {
+            age_years: 70,
+            sex: Sex::Female,
+            congestive_heart_failure: false,
+            hypertension: true,
+            diabetes: false,
+            stroke_or_tia: false,
+            vascular_disease: false,
+        };
+        let result = calculate_cha2ds2_vasc(cha2ds2_vasc.clone()).unwrap();
+        assert_eq!(result.score, 3);
+        assert_eq!(result.recommendation, AnticoagulationRecommendation::Recommended);
+        let result = calculate_cha2ds2_vasc(Cha2ds2VascInput { age_years: 50, ..cha2ds2_vasc }).unwrap();
+        assert_eq!(result.score, 2);
+        assert_eq!(result.recommendation, AnticoagulationRecommendation::Consider);
+    }
+}
@@ -0,0 +1,17 @@
+//! Clinical calculations
+//!
+//! Scores, equations and growth percentiles shared by the server and the
+//! mobile app, which calls them through the uniffi bindings:
+//! - BMI with the WHO adult classification
+//! - eGFR by CKD-EPI 2021 with the KDIGO GFR category
+//! - Pediatric growth percentiles against the WHO and CDC references
+//! - Apgar and CHA₂DS₂-VASc scores
+//! - UCUM unit conversion of every measured input
+
+pub mod calculators;
+pub mod growth;
+pub mod units;
+
+pub use calculators::*;
+pub use growth::*;
+pub use units::*;
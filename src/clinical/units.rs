@@ -0,0 +1,106 @@
+//! UCUM units accepted by the clinical calculators
+//!
+//! Calculator inputs carry the UCUM code they were measured in; each
+//! equation works in one canonical unit per dimension and converts on entry
+//! with `Ucum`.
+
+use crate::standards::ucum::Ucum;
+use crate::HimsError;
+
+/// A measured value and the UCUM code of its unit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub value: f64,
+    pub unit: String,
+}
+
+impl Measurement {
+    pub fn new(value: f64, unit: &str) -> Self {
+        Self { value, unit: unit.to_string() }
+    }
+}
+
+/// What a measurement measures, which fixes its canonical unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// Body mass, in kg
+    Mass,
+    /// Body length, height or circumference, in cm
+    Length,
+    /// Serum creatinine, in mg/dL
+    Creatinine,
+    /// Body mass index, in kg/m2
+    BodyMassIndex,
+}
+
+/// Molar mass of creatinine, g/mol, for molar creatinine results
+const CREATININE_MOLAR_MASS: f64 = 113.12;
+
+impl Dimension {
+    /// UCUM codes accepted for the dimension, the canonical one first
+    fn units(self) -> &'static [&'static str] {
+        match self {
+            Dimension::Mass => &["kg", "g", "[lb_av]", "[oz_av]"],
+            Dimension::Length => &["cm", "m", "mm", "[in_i]", "[ft_i]"],
+            Dimension::Creatinine => &["mg/dL", "mg/L", "umol/L", "mmol/L"],
+            Dimension::BodyMassIndex => &["kg/m2"],
+        }
+    }
+
+    /// UCUM code of the unit the calculators work in
+    pub fn canonical_unit(self) -> &'static str {
+        self.units()[0]
+    }
+
+    /// UCUM codes accepted for the dimension
+    pub fn accepted_units(self) -> Vec<&'static str> {
+        self.units().to_vec()
+    }
+}
+
+/// `measurement` in the canonical unit of `dimension`; `field` names the
+/// input in the error when the unit is not accepted or the value is not
+/// a positive number
+pub fn to_canonical(field: &str, measurement: &Measurement, dimension: Dimension) -> Result<f64, HimsError> {
+    let invalid = |message: String| HimsError::ValidationError { field: field.to_string(), message };
+
+    // UCUM codes are case-sensitive, but "umol/L" is also written with the
+    // micro sign in lab feeds
+    let unit = measurement.unit.trim().replace('µ', "u");
+    if !dimension.units().contains(&unit.as_str()) {
+        return Err(invalid(format!(
+            "unit must be one of {}, not {:?}",
+            dimension.accepted_units().join(", "),
+            measurement.unit
+        )));
+    }
+    if !measurement.value.is_finite() || measurement.value <= 0.0 {
+        return Err(invalid(format!("{} is not a positive number", measurement.value)));
+    }
+    let canonical = dimension.canonical_unit();
+    let converted = match dimension {
+        Dimension::Creatinine => {
+            Ucum::convert_substance(measurement.value, &unit, canonical, CREATININE_MOLAR_MASS)
+        }
+        _ => Ucum::convert(measurement.value, &unit, canonical),
+    };
+    converted.map_err(|e| invalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+        let creatinine = to_canonical("creatinine", &Measurement::new(88.4, "µmol/L"), Dimension::Creatinine).unwrap();
+        assert!((creatinine - 1.0).abs() < 1e-3);
+        assert!(close(to_canonical("weight", &Measurement::new(154.0, "[lb_av]"), Dimension::Mass).unwrap(), 69.853_225));
+        assert!(close(to_canonical("height", &Measurement::new(1.8, "m"), Dimension::Length).unwrap(), 180.0));
+
+        assert!(to_canonical("height", &Measurement::new(180.0, "kg"), Dimension::Length).is_err());
+        assert!(to_canonical("height", &Measurement::new(-1.0, "cm"), Dimension::Length).is_err());
+        assert!(to_canonical("height", &Measurement::new(f64::NAN, "cm"), Dimension::Length).is_err());
+    }
+}
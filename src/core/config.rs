@@ -1,23 +1,40 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "server")]
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "server")]
+use tokio::sync::watch;
+
+use crate::core::HimsError;
+
+/// Prefix of environment variables read by `ConfigLoader`
+pub const ENV_PREFIX: &str = "HIMS_";
 
 /// HIMS SDK Configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
 pub struct HimsConfig {
     pub api_endpoint: String,
     pub auth_token: Option<String>,
     pub enable_logging: bool,
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
     pub environment: Environment,
     pub security_settings: SecuritySettings,
+    /// ISO 3166-1 alpha-2 code of the deployment's country; selects the FHIR
+    /// profile pack resources must conform to (`US` for US Core, `IN` for ABDM)
+    #[serde(default)]
+    pub country_code: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Environment {
     Development,
     Testing,
     Production,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SecuritySettings {
     pub enable_audit_logging: bool,
     pub enable_encryption: bool,
@@ -30,12 +47,440 @@ impl Default for HimsConfig {
             api_endpoint: "https://api.example.com/fhir".to_string(),
             auth_token: None,
             enable_logging: true,
+            log_level: default_log_level(),
             environment: Environment::Development,
             security_settings: SecuritySettings {
                 enable_audit_logging: true,
                 enable_encryption: true,
                 audit_retention_days: 2555, // 7 years for healthcare data
             },
+            country_code: None,
+        }
+    }
+}
+
+/// Secrets are redacted so configs can be logged safely
+impl std::fmt::Debug for HimsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HimsConfig")
+            .field("api_endpoint", &self.api_endpoint)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| "[REDACTED]"))
+            .field("enable_logging", &self.enable_logging)
+            .field("log_level", &self.log_level)
+            .field("environment", &self.environment)
+            .field("security_settings", &self.security_settings)
+            .field("country_code", &self.country_code)
+            .finish()
+    }
+}
+
+impl HimsConfig {
+    /// Reject settings that are invalid or unsafe for the environment
+    pub fn validate(&self) -> Result<(), HimsError> {
+        let invalid = |message: String| Err(HimsError::ConfigurationError { message });
+
+        if !(self.api_endpoint.starts_with("https://") || self.api_endpoint.starts_with("http://")) {
+            return invalid(format!("api_endpoint must be an http(s) URL: {}", self.api_endpoint));
+        }
+        if !["trace", "debug", "info", "warn", "error"].contains(&self.log_level.as_str()) {
+            return invalid(format!("Unknown log_level: {}", self.log_level));
+        }
+        if self.security_settings.audit_retention_days == 0 {
+            return invalid("security_settings.audit_retention_days must be positive".to_string());
+        }
+        if let Some(code) = &self.country_code {
+            if code.len() != 2 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+                return invalid(format!("country_code must be an ISO 3166-1 alpha-2 code: {}", code));
+            }
+        }
+
+        if self.environment == Environment::Production {
+            if !self.api_endpoint.starts_with("https://") {
+                return invalid("api_endpoint must use https in production".to_string());
+            }
+            if !self.security_settings.enable_encryption {
+                return invalid("Encryption cannot be disabled in production".to_string());
+            }
+            if !self.security_settings.enable_audit_logging {
+                return invalid("Audit logging cannot be disabled in production".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy settings that can change without a restart from `other`.
+    /// Returns the names of settings that differ but need a restart.
+    pub fn apply_non_critical(&mut self, other: &HimsConfig) -> Vec<&'static str> {
+        self.enable_logging = other.enable_logging;
+        self.log_level = other.log_level.clone();
+
+        let mut restart_required = Vec::new();
+        if self.api_endpoint != other.api_endpoint {
+            restart_required.push("api_endpoint");
+        }
+        if self.auth_token != other.auth_token {
+            restart_required.push("auth_token");
+        }
+        if self.environment != other.environment {
+            restart_required.push("environment");
+        }
+        if self.security_settings != other.security_settings {
+            restart_required.push("security_settings");
+        }
+        if self.country_code != other.country_code {
+            restart_required.push("country_code");
+        }
+        restart_required
+    }
+}
+
+/// Builds a `HimsConfig` from layered sources. Later layers win: defaults,
+/// then config files in the order added, then `HIMS_*` environment
+/// variables, then command line overrides.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    files: Vec<PathBuf>,
+    use_env: bool,
+    overrides: Vec<(String, String)>,
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a TOML, YAML or JSON file, chosen by extension
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Read `HIMS_*` variables; nested keys use a double underscore, e.g.
+    /// `HIMS_SECURITY_SETTINGS__AUDIT_RETENTION_DAYS`
+    pub fn with_env(mut self) -> Self {
+        self.use_env = true;
+        self
+    }
+
+    /// Set a dotted key, e.g. `security_settings.enable_encryption`
+    pub fn with_override(mut self, key: &str, value: &str) -> Self {
+        self.overrides.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Take `--config <file>` and `--set <key>=<value>` from command line
+    /// arguments; other arguments are ignored
+    pub fn with_cli_args<I: IntoIterator<Item = String>>(mut self, args: I) -> Result<Self, HimsError> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let path = args.next().ok_or_else(|| missing_argument("--config"))?;
+                    self.files.push(PathBuf::from(path));
+                }
+                "--set" => {
+                    let assignment = args.next().ok_or_else(|| missing_argument("--set"))?;
+                    let (key, value) = assignment.split_once('=').ok_or_else(|| HimsError::ConfigurationError {
+                        message: format!("Expected key=value after --set, got: {}", assignment),
+                    })?;
+                    self.overrides.push((key.to_string(), value.to_string()));
+                }
+                _ => {}
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn load(&self) -> Result<HimsConfig, HimsError> {
+        let mut merged = serde_json::to_value(HimsConfig::default()).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Failed to serialize default configuration: {}", e),
+        })?;
+
+        for path in &self.files {
+            merge(&mut merged, read_file(path)?);
+        }
+
+        if self.use_env {
+            for (name, value) in std::env::vars() {
+                if let Some(key) = name.strip_prefix(ENV_PREFIX) {
+                    let key = key.to_ascii_lowercase().replace("__", ".");
+                    set_path(&mut merged, &key, parse_scalar(&value));
+                }
+            }
         }
+
+        for (key, value) in &self.overrides {
+            set_path(&mut merged, key, parse_scalar(value));
+        }
+
+        let config: HimsConfig = serde_json::from_value(merged).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Invalid configuration: {}", e),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reload whenever a config file changes and publish the result.
+    /// Only non-critical settings are applied; changes to other settings
+    /// are logged as requiring a restart. Invalid files are ignored.
+    #[cfg(feature = "server")]
+    pub fn watch(self, initial: HimsConfig, interval: Duration) -> watch::Receiver<HimsConfig> {
+        let (sender, receiver) = watch::channel(initial);
+
+        tokio::spawn(async move {
+            let mut last_modified = self.modified_times();
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let modified = self.modified_times();
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+
+                let reloaded = match self.load() {
+                    Ok(config) => config,
+                    Err(e) => {
+                        tracing::warn!("Ignoring invalid configuration change: {}", e);
+                        continue;
+                    }
+                };
+
+                let mut current = sender.borrow().clone();
+                let restart_required = current.apply_non_critical(&reloaded);
+                if !restart_required.is_empty() {
+                    tracing::warn!("Configuration changes require a restart: {}", restart_required.join(", "));
+                }
+                if sender.send(current).is_err() {
+                    break;
+                }
+            }
+        });
+
+        receiver
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "server")]
+    fn modified_times(&self) -> Vec<Option<SystemTime>> {
+        self.files
+            .iter()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn missing_argument(flag: &str) -> HimsError {
+    HimsError::ConfigurationError {
+        message: format!("Missing value for {}", flag),
+    }
+}
+
+fn read_file(path: &Path) -> Result<Value, HimsError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| HimsError::ConfigurationError {
+        message: format!("Failed to read config file {}: {}", path.display(), e),
+    })?;
+    let parse_error = |e: String| HimsError::ConfigurationError {
+        message: format!("Failed to parse config file {}: {}", path.display(), e),
+    };
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&contents).map_err(|e| parse_error(e.to_string())),
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&contents).map_err(|e| parse_error(e.to_string())),
+        Some("json") => serde_json::from_str(&contents).map_err(|e| parse_error(e.to_string())),
+        _ => Err(HimsError::ConfigurationError {
+            message: format!("Unsupported config file type: {}", path.display()),
+        }),
+    }
+}
+
+/// Deep-merge `overlay` into `base`, replacing non-object values
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn set_path(root: &mut Value, dotted_key: &str, value: Value) {
+    let mut current = root;
+    let mut parts = dotted_key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        if !current.is_object() {
+            *current = Value::Object(serde_json::Map::new());
+        }
+        let object = current.as_object_mut().expect("value was just made an object");
+        if parts.peek().is_none() {
+            object.insert(part.to_string(), value);
+            return;
+        }
+        current = object.entry(part.to_string()).or_insert(Value::Null);
+    }
+}
+
+/// Interpret an environment or CLI value as a bool or number when it looks
+/// like one, otherwise as a string
+fn parse_scalar(value: &str) -> Value {
+    match serde_json::from_str::<Value>(value) {
+        Ok(parsed @ (Value::Bool(_) | Value::Number(_) | Value::Null)) => parsed,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    fn write(dir: &tempfile::TempDir, name: &str, contents: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_file_layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml = write(
+            &dir,
+            "hims.toml",
+            "api_endpoint = \"https://fhir.example.org\"\nlog_level = \"debug\"\n\n\
+             [security_settings]\naudit_retention_days = 3650\n",
+        );
+        let yaml = write(&dir, "site.yaml", "log_level: warn\ncountry_code: IN\n");
+
+        let config = ConfigLoader::new().with_file(&toml).with_file(&yaml).load().unwrap();
+        assert_eq!(config.api_endpoint, "https://fhir.example.org");
+        assert_eq!(config.log_level, "warn");
+        assert_eq!(config.country_code.as_deref(), Some("IN"));
+        // Nested tables merge into the defaults rather than replacing them
+        assert_eq!(config.security_settings.audit_retention_days, 3650);
+        assert!(config.security_settings.enable_encryption);
+
+        let json = write(&dir, "override.json", "{\"environment\": \"Testing\"}");
+        let config = ConfigLoader::new().with_file(&toml).with_file(&json).load().unwrap();
+        assert_eq!(config.environment, Environment::Testing);
+        assert_eq!(config.log_level, "debug");
+    }
+
+    #[test]
+    fn test_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let ini = write(&dir, "hims.ini", "log_level=debug");
+        let broken = write(&dir, "broken.toml", "log_level = ");
+        for path in [ini, broken, dir.path().join("missing.toml")] {
+            assert!(matches!(
+                ConfigLoader::new().with_file(&path).load(),
+                Err(HimsError::ConfigurationError { .. })
+            ));
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_env_and_overrides() {
+        std::env::set_var("HIMS_LOG_LEVEL", "error");
+        std::env::set_var("HIMS_SECURITY_SETTINGS__AUDIT_RETENTION_DAYS", "4000");
+        let from_env = ConfigLoader::new().with_env().load();
+        let overridden = ConfigLoader::new()
+            .with_env()
+            .with_override("log_level", "trace")
+            .with_override("security_settings.enable_audit_logging", "false")
+            .load();
+        let without_env = ConfigLoader::new().load();
+        std::env::remove_var("HIMS_LOG_LEVEL");
+        std::env::remove_var("HIMS_SECURITY_SETTINGS__AUDIT_RETENTION_DAYS");
+
+        let from_env = from_env.unwrap();
+        assert_eq!(from_env.log_level, "error");
+        assert_eq!(from_env.security_settings.audit_retention_days, 4000);
+        let overridden = overridden.unwrap();
+        assert_eq!(overridden.log_level, "trace");
+        assert!(!overridden.security_settings.enable_audit_logging);
+        assert_eq!(overridden.security_settings.audit_retention_days, 4000);
+        assert_eq!(without_env.unwrap().log_level, "info");
+    }
+
+    #[test]
+    fn test_cli_args() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = write(&dir, "hims.toml", "log_level = \"debug\"\n");
+        let args = [
+            "hims-server",
+            "--port",
+            "8080",
+            "--config",
+            file.to_str().unwrap(),
+            "--set",
+            "api_endpoint=https://fhir.example.org/r4",
+        ];
+        let config = ConfigLoader::new()
+            .with_cli_args(args.iter().map(|arg| arg.to_string()))
+            .unwrap()
+            .load()
+            .unwrap();
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.api_endpoint, "https://fhir.example.org/r4");
+
+        for args in [vec!["--config"], vec!["--set"], vec!["--set", "log_level"]] {
+            assert!(ConfigLoader::new().with_cli_args(args.into_iter().map(String::from)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(HimsConfig::default().validate().is_ok());
+        let invalid = [
+            ("api_endpoint", "ftp://fhir.example.org"),
+            ("log_level", "verbose"),
+            ("security_settings.audit_retention_days", "0"),
+            ("country_code", "usa"),
+        ];
+        for (key, value) in invalid {
+            assert!(ConfigLoader::new().with_override(key, value).load().is_err(), "{}", key);
+        }
+
+        // Production refuses plain http and disabled safeguards
+        let production = ConfigLoader::new().with_override("environment", "Production");
+        assert!(production.load().is_ok());
+        assert!(production.clone().with_override("api_endpoint", "http://fhir.internal").load().is_err());
+        assert!(production.clone().with_override("security_settings.enable_encryption", "false").load().is_err());
+        assert!(production.with_override("security_settings.enable_audit_logging", "false").load().is_err());
+    }
+
+    #[test]
+    fn test_debug_redacts_token() {
+        let config = HimsConfig {
+            auth_token: Some("secret-token-value".to_string()),
+            ..HimsConfig::default()
+        };
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("[REDACTED]"));
+        assert!(!debug.contains("secret-token-value"));
+    }
+
+    #[test]
+    fn test_apply_non_critical() {
+        let mut current = HimsConfig::default();
+        let reloaded = HimsConfig {
+            log_level: "debug".to_string(),
+            enable_logging: false,
+            api_endpoint: "https://fhir.example.org".to_string(),
+            country_code: Some("US".to_string()),
+            ..HimsConfig::default()
+        };
+
+        let restart_required = current.apply_non_critical(&reloaded);
+        assert_eq!(restart_required, vec!["api_endpoint", "country_code"]);
+        assert_eq!(current.log_level, "debug");
+        assert!(!current.enable_logging);
+        assert_eq!(current.api_endpoint, "https://api.example.com/fhir");
+    }
+}
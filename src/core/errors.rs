@@ -30,4 +30,12 @@ pub enum HimsError {
     
     #[error("Security error: {message}")]
     SecurityError { message: String },
+
+    /// A conditional request's precondition (e.g. `If-Match`) does not hold
+    #[error("Precondition failed: {message}")]
+    PreconditionFailed { message: String },
+
+    /// A concurrent change won; the client must re-read and retry
+    #[error("Conflict: {message}")]
+    ConflictError { message: String },
 }
\ No newline at end of file
@@ -1,11 +1,121 @@
+use axum::{
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
 use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::net::UdpSocket;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::Instrument;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::security::phi_detection::PhiDetector;
+
+/// Header carrying the correlation ID across services
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+/// Where log lines are written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LogSink {
+    Stdout,
+    File {
+        directory: PathBuf,
+        prefix: String,
+        rotation: LogRotation,
+    },
+    /// RFC 5424 messages over UDP, e.g. `127.0.0.1:514`
+    Syslog { address: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Default level for all targets
+    pub level: String,
+    /// Per-module levels, e.g. `hims_core_sdk::modules::auth` => `debug`
+    pub module_levels: HashMap<String, String>,
+    pub format: LogFormat,
+    pub sinks: Vec<LogSink>,
+    /// Mask PHI in every line before it reaches a sink
+    pub redact_phi: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            module_levels: HashMap::new(),
+            format: LogFormat::Text,
+            sinks: vec![LogSink::Stdout],
+            redact_phi: true,
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn env_filter(&self) -> Result<EnvFilter, HimsError> {
+        let mut directives = vec![self.level.clone()];
+        let mut modules: Vec<_> = self.module_levels.iter().collect();
+        modules.sort();
+        directives.extend(modules.into_iter().map(|(module, level)| format!("{}={}", module, level)));
+
+        EnvFilter::try_new(directives.join(",")).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Invalid log level configuration: {}", e),
+        })
+    }
+}
 
 /// HIMS SDK Logger
 pub struct HimsLogger;
 
 impl HimsLogger {
+    /// Install the process-wide subscriber. `tracing` events and `log`
+    /// records both go through it, so every line is PHI-redacted.
+    pub fn init_with(config: &LoggingConfig) -> Result<(), HimsError> {
+        let writer = RedactingMakeWriter::new(config)?;
+        let builder = tracing_subscriber::fmt()
+            .with_env_filter(config.env_filter()?)
+            .with_writer(writer)
+            .with_ansi(false);
+
+        let result = match config.format {
+            LogFormat::Json => builder.json().with_current_span(true).try_init(),
+            LogFormat::Text => builder.try_init(),
+        };
+        result.map_err(|e| HimsError::ConfigurationError {
+            message: format!("Failed to install logger: {}", e),
+        })
+    }
+
     pub fn init() {
-        env_logger::init();
+        if let Err(e) = Self::init_with(&LoggingConfig::default()) {
+            eprintln!("{}", e);
+        }
     }
 
     pub fn log_info(message: &str) {
@@ -19,4 +129,292 @@ impl HimsLogger {
     pub fn log_error(message: &str) {
         error!("{}", message);
     }
-}
\ No newline at end of file
+}
+
+/// Middleware giving each request a correlation ID, taken from the
+/// `X-Correlation-ID` header when present, and logging the request under it
+pub async fn correlation_id(mut request: Request, next: Next) -> Result<Response, StatusCode> {
+    let correlation_id = request
+        .headers()
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let header = HeaderValue::from_str(&correlation_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    request.headers_mut().insert(CORRELATION_ID_HEADER, header.clone());
+
+    let span = tracing::info_span!(
+        "request",
+        correlation_id = %correlation_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let mut response = next.run(request).instrument(span).await;
+    response.headers_mut().insert(CORRELATION_ID_HEADER, header);
+    Ok(response)
+}
+
+enum SinkWriter {
+    Stdout,
+    File(Mutex<RotatingFile>),
+    Syslog { socket: UdpSocket, address: String },
+}
+
+impl SinkWriter {
+    fn write_line(&self, line: &str) -> io::Result<()> {
+        match self {
+            SinkWriter::Stdout => io::stdout().lock().write_all(line.as_bytes()),
+            SinkWriter::File(file) => file
+                .lock()
+                .map_err(|_| io::Error::other("log file lock poisoned"))?
+                .write_line(line),
+            SinkWriter::Syslog { socket, address } => {
+                // <134> = facility local0, severity informational
+                let message = format!(
+                    "<134>1 {} - hims-core - - - {}",
+                    Utc::now().to_rfc3339(),
+                    line.trim_end()
+                );
+                socket.send_to(message.as_bytes(), address).map(|_| ())
+            }
+        }
+    }
+}
+
+/// Appends to `<prefix>.<period>.log`, starting a new file each period
+struct RotatingFile {
+    directory: PathBuf,
+    prefix: String,
+    rotation: LogRotation,
+    period: String,
+    file: Option<File>,
+}
+
+impl RotatingFile {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let period = match self.rotation {
+            LogRotation::Hourly => Utc::now().format("%Y-%m-%d-%H").to_string(),
+            LogRotation::Daily => Utc::now().format("%Y-%m-%d").to_string(),
+            LogRotation::Never => String::new(),
+        };
+        if self.file.is_none() || period != self.period {
+            let name = if period.is_empty() {
+                format!("{}.log", self.prefix)
+            } else {
+                format!("{}.{}.log", self.prefix, period)
+            };
+            std::fs::create_dir_all(&self.directory)?;
+            self.file = Some(OpenOptions::new().create(true).append(true).open(self.directory.join(name))?);
+            self.period = period;
+        }
+        self.file.as_mut().expect("log file was just opened").write_all(line.as_bytes())
+    }
+}
+
+#[derive(Clone)]
+struct RedactingMakeWriter {
+    sinks: Arc<Vec<SinkWriter>>,
+    detector: Option<PhiDetector>,
+}
+
+impl RedactingMakeWriter {
+    fn new(config: &LoggingConfig) -> Result<Self, HimsError> {
+        let sinks = config
+            .sinks
+            .iter()
+            .map(|sink| match sink {
+                LogSink::Stdout => Ok(SinkWriter::Stdout),
+                LogSink::File { directory, prefix, rotation } => Ok(SinkWriter::File(Mutex::new(RotatingFile {
+                    directory: directory.clone(),
+                    prefix: prefix.clone(),
+                    rotation: *rotation,
+                    period: String::new(),
+                    file: None,
+                }))),
+                LogSink::Syslog { address } => UdpSocket::bind("0.0.0.0:0")
+                    .map(|socket| SinkWriter::Syslog {
+                        socket,
+                        address: address.clone(),
+                    })
+                    .map_err(|e| HimsError::ConfigurationError {
+                        message: format!("Failed to open syslog socket: {}", e),
+                    }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            sinks: Arc::new(sinks),
+            detector: config.redact_phi.then(PhiDetector::new),
+        })
+    }
+}
+
+impl<'a> MakeWriter<'a> for RedactingMakeWriter {
+    type Writer = RedactingWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            sinks: self.sinks.clone(),
+            detector: self.detector.clone(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Buffers one formatted event and redacts it as a whole when dropped, so
+/// values split across writes are still caught
+struct RedactingWriter {
+    sinks: Arc<Vec<SinkWriter>>,
+    detector: Option<PhiDetector>,
+    buffer: Vec<u8>,
+}
+
+impl Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RedactingWriter {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let line = String::from_utf8_lossy(&self.buffer);
+        let line = match &self.detector {
+            Some(detector) => detector.redact(&line),
+            None => line.into_owned(),
+        };
+        for sink in self.sinks.iter() {
+            if let Err(e) = sink.write_line(&line) {
+                eprintln!("Failed to write log line: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::Service;
+
+    fn file_sink(directory: &std::path::Path, rotation: LogRotation) -> LogSink {
+        LogSink::File {
+            directory: directory.to_path_buf(),
+            prefix: "hims".to_string(),
+            rotation,
+        }
+    }
+
+    fn write_event(writer: &RedactingMakeWriter, chunks: &[&str]) {
+        let mut event = writer.make_writer();
+        for chunk in chunks {
+            event.write_all(chunk.as_bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_env_filter() {
+        let mut config = LoggingConfig::default();
+        config.module_levels.insert("hims_core_sdk::modules::auth".to_string(), "debug".to_string());
+        assert_eq!(
+            config.env_filter().unwrap().to_string(),
+            "hims_core_sdk::modules::auth=debug,info"
+        );
+
+        config.level = "loud".to_string();
+        config.module_levels.insert("hims_core_sdk::sync".to_string(), "very=loud=please".to_string());
+        assert!(matches!(config.env_filter(), Err(HimsError::ConfigurationError { .. })));
+    }
+
+    #[test]
+    fn test_file_sink_redacts_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LoggingConfig {
+            sinks: vec![file_sink(dir.path(), LogRotation::Never)],
+            ..LoggingConfig::default()
+        };
+        let writer = RedactingMakeWriter::new(&config).unwrap();
+        // A value split across writes is still caught once the event ends
+        write_event(&writer, &["INFO patient updated ssn=123-4", "5-6789\n"]);
+        write_event(&writer, &["INFO no identifiers here\n"]);
+        write_event(&writer, &[]);
+
+        let logged = std::fs::read_to_string(dir.path().join("hims.log")).unwrap();
+        assert_eq!(logged, "INFO patient updated ssn=[REDACTED:ssn]\nINFO no identifiers here\n");
+    }
+
+    #[test]
+    fn test_redaction_can_be_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LoggingConfig {
+            sinks: vec![file_sink(dir.path(), LogRotation::Daily)],
+            redact_phi: false,
+            ..LoggingConfig::default()
+        };
+        let writer = RedactingMakeWriter::new(&config).unwrap();
+        write_event(&writer, &["DEBUG mrn=A77\n"]);
+
+        let name = format!("hims.{}.log", Utc::now().format("%Y-%m-%d"));
+        assert_eq!(std::fs::read_to_string(dir.path().join(name)).unwrap(), "DEBUG mrn=A77\n");
+    }
+
+    #[test]
+    fn test_sink_config() {
+        let config: LoggingConfig = serde_json::from_value(serde_json::json!({
+            "level": "warn",
+            "module_levels": {},
+            "format": "json",
+            "sinks": [
+                { "type": "stdout" },
+                { "type": "file", "directory": "/var/log/hims", "prefix": "api", "rotation": "hourly" },
+                { "type": "syslog", "address": "127.0.0.1:514" }
+            ],
+            "redact_phi": true
+        }))
+        .unwrap();
+        assert_eq!(config.format, LogFormat::Json);
+        assert!(matches!(config.sinks[1], LogSink::File { rotation: LogRotation::Hourly, .. }));
+        assert!(matches!(&config.sinks[2], LogSink::Syslog { address } if address == "127.0.0.1:514"));
+        assert_eq!(RedactingMakeWriter::new(&config).unwrap().sinks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id() {
+        // Router is always ready, so requests can be sent without polling
+        let mut app = Router::new()
+            .route("/ping", get(|request: Request| async move {
+                request.headers()[CORRELATION_ID_HEADER].to_str().unwrap().to_string()
+            }))
+            .layer(axum::middleware::from_fn(correlation_id));
+
+        let request = Request::builder()
+            .uri("/ping")
+            .header(CORRELATION_ID_HEADER, "trace-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.headers()[CORRELATION_ID_HEADER], "trace-123");
+        let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], b"trace-123");
+
+        // Missing or oversized IDs are replaced with a fresh UUID
+        for supplied in [None, Some("x".repeat(129))] {
+            let mut request = Request::builder().uri("/ping");
+            if let Some(value) = &supplied {
+                request = request.header(CORRELATION_ID_HEADER, value.as_str());
+            }
+            let response = app.call(request.body(Body::empty()).unwrap()).await.unwrap();
+            let generated = response.headers()[CORRELATION_ID_HEADER].to_str().unwrap();
+            assert!(Uuid::parse_str(generated).is_ok());
+        }
+    }
+}
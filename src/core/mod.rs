@@ -1,11 +1,13 @@
 pub mod auth;
 pub mod config;
 pub mod errors;
+#[cfg(feature = "server")]
 pub mod logger;
 pub mod utils;
 
 pub use auth::*;
 pub use config::*;
 pub use errors::*;
+#[cfg(feature = "server")]
 pub use logger::*;
 pub use utils::*;
\ No newline at end of file
@@ -0,0 +1,39 @@
+use crate::countries::{CountryConfig, RegulatoryFramework, AuditRequirements};
+use crate::countries::compliance::requirements_for_standards;
+
+/// Get Australia configuration
+pub fn get_australia_config() -> CountryConfig {
+    CountryConfig {
+        country_code: "AU".to_string(),
+        country_name: "Australia".to_string(),
+        regulatory_framework: RegulatoryFramework {
+            primary_authority: "Office of the Australian Information Commissioner".to_string(),
+            requirements: requirements_for_standards(&[
+                "Privacy Act 1988 (Australian Privacy Principles)",
+                "My Health Records Act 2012",
+                "Healthcare Identifiers Act 2010",
+                "Notifiable Data Breaches scheme",
+            ]),
+            audit_requirements: AuditRequirements {
+                // State health records acts require at least 7 years for adults
+                retention_period_years: 7,
+                real_time_monitoring: true,
+                third_party_audit_required: false,
+            },
+        },
+        // My Health Records Act s77: records must be held in Australia
+        data_localization_required: true,
+        supported_standards: vec![
+            "FHIR R4".to_string(),
+            "AU Core FHIR profiles".to_string(),
+            "HL7v2".to_string(),
+            "SNOMED CT-AU".to_string(),
+            "AMT".to_string(),
+            "ICD-10-AM".to_string(),
+        ],
+        privacy_regulations: vec![
+            "Privacy Act 1988".to_string(),
+            "My Health Records Act 2012".to_string(),
+        ],
+    }
+}
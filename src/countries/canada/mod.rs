@@ -0,0 +1,41 @@
+pub mod provinces;
+
+use crate::countries::{CountryConfig, RegulatoryFramework, AuditRequirements};
+use crate::countries::compliance::requirements_for_standards;
+
+/// Get Canada configuration
+pub fn get_canada_config() -> CountryConfig {
+    CountryConfig {
+        country_code: "CA".to_string(),
+        country_name: "Canada".to_string(),
+        regulatory_framework: RegulatoryFramework {
+            primary_authority: "Office of the Privacy Commissioner of Canada".to_string(),
+            requirements: requirements_for_standards(&[
+                "PIPEDA",
+                "Privacy Act",
+                "Canada Health Infoway Privacy and Security Conceptual Architecture",
+            ]),
+            audit_requirements: AuditRequirements {
+                // Provincial colleges commonly require 10 years after the last entry
+                retention_period_years: 10,
+                real_time_monitoring: true,
+                third_party_audit_required: false,
+            },
+        },
+        data_localization_required: false,
+        supported_standards: vec![
+            "FHIR R4".to_string(),
+            "CA Baseline FHIR profiles".to_string(),
+            "HL7v2".to_string(),
+            "SNOMED CT CA".to_string(),
+            "ICD-10-CA".to_string(),
+            "CCI".to_string(),
+        ],
+        privacy_regulations: vec![
+            "PIPEDA".to_string(),
+            "Provincial health information acts".to_string(),
+        ],
+    }
+}
+
+pub use provinces::*;
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use crate::core::HimsError;
+
+/// Provincial or territorial health privacy configuration. Where a
+/// province has health information legislation deemed substantially similar,
+/// it applies to custodians instead of PIPEDA.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvincialConfig {
+    pub province_code: String,
+    pub province_name: String,
+    pub health_privacy_act: String,
+    pub commissioner: String,
+    pub substantially_similar_to_pipeda: bool,
+    pub breach_notification_to_commissioner: bool,
+    pub record_retention_years: u32,
+}
+
+/// Registry of Canadian provincial health privacy regimes
+pub struct CanadianProvinceRegistry {
+    provinces: std::collections::HashMap<String, ProvincialConfig>,
+}
+
+impl CanadianProvinceRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            provinces: std::collections::HashMap::new(),
+        };
+        registry.initialize_provinces();
+        registry
+    }
+
+    fn initialize_provinces(&mut self) {
+        self.register_province(ProvincialConfig {
+            province_code: "ON".to_string(),
+            province_name: "Ontario".to_string(),
+            health_privacy_act: "Personal Health Information Protection Act, 2004 (PHIPA)".to_string(),
+            commissioner: "Information and Privacy Commissioner of Ontario".to_string(),
+            substantially_similar_to_pipeda: true,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 10,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "AB".to_string(),
+            province_name: "Alberta".to_string(),
+            health_privacy_act: "Health Information Act (HIA)".to_string(),
+            commissioner: "Office of the Information and Privacy Commissioner of Alberta".to_string(),
+            substantially_similar_to_pipeda: false,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 10,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "BC".to_string(),
+            province_name: "British Columbia".to_string(),
+            health_privacy_act: "E-Health (Personal Health Information Access and Protection of Privacy) Act".to_string(),
+            commissioner: "Office of the Information and Privacy Commissioner for British Columbia".to_string(),
+            substantially_similar_to_pipeda: false,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 16,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "QC".to_string(),
+            province_name: "Quebec".to_string(),
+            health_privacy_act: "Act respecting health and social services information (Law 5)".to_string(),
+            commissioner: "Commission d'accès à l'information du Québec".to_string(),
+            substantially_similar_to_pipeda: true,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 5,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "NS".to_string(),
+            province_name: "Nova Scotia".to_string(),
+            health_privacy_act: "Personal Health Information Act (PHIA)".to_string(),
+            commissioner: "Office of the Information and Privacy Commissioner for Nova Scotia".to_string(),
+            substantially_similar_to_pipeda: true,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 10,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "NB".to_string(),
+            province_name: "New Brunswick".to_string(),
+            health_privacy_act: "Personal Health Information Privacy and Access Act (PHIPAA)".to_string(),
+            commissioner: "Office of the Ombud New Brunswick".to_string(),
+            substantially_similar_to_pipeda: true,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 10,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "NL".to_string(),
+            province_name: "Newfoundland and Labrador".to_string(),
+            health_privacy_act: "Personal Health Information Act (PHIA)".to_string(),
+            commissioner: "Office of the Information and Privacy Commissioner of Newfoundland and Labrador".to_string(),
+            substantially_similar_to_pipeda: true,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 10,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "MB".to_string(),
+            province_name: "Manitoba".to_string(),
+            health_privacy_act: "Personal Health Information Act (PHIA)".to_string(),
+            commissioner: "Manitoba Ombudsman".to_string(),
+            substantially_similar_to_pipeda: false,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 10,
+        });
+        self.register_province(ProvincialConfig {
+            province_code: "SK".to_string(),
+            province_name: "Saskatchewan".to_string(),
+            health_privacy_act: "Health Information Protection Act (HIPA)".to_string(),
+            commissioner: "Office of the Saskatchewan Information and Privacy Commissioner".to_string(),
+            substantially_similar_to_pipeda: false,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 6,
+        });
+    }
+
+    pub fn register_province(&mut self, config: ProvincialConfig) {
+        self.provinces.insert(config.province_code.clone(), config);
+    }
+
+    pub fn get_province_config(&self, province_code: &str) -> Result<&ProvincialConfig, HimsError> {
+        self.provinces.get(province_code).ok_or_else(|| HimsError::ConfigurationError {
+            message: format!("Canadian province configuration not found for: {}", province_code),
+        })
+    }
+
+    /// All registered provinces, sorted by code
+    pub fn list_provinces(&self) -> Vec<&ProvincialConfig> {
+        let mut provinces: Vec<&ProvincialConfig> = self.provinces.values().collect();
+        provinces.sort_by(|a, b| a.province_code.cmp(&b.province_code));
+        provinces
+    }
+
+    /// Privacy law governing a custodian in the province: the provincial act
+    /// where one exists, otherwise PIPEDA
+    pub fn governing_privacy_law(&self, province_code: &str) -> String {
+        self.provinces
+            .get(province_code)
+            .map(|config| config.health_privacy_act.clone())
+            .unwrap_or_else(|| "PIPEDA".to_string())
+    }
+}
+
+impl Default for CanadianProvinceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provinces() {
+        let registry = CanadianProvinceRegistry::new();
+        let codes: Vec<&str> = registry.list_provinces().iter().map(|p| p.province_code.as_str()).collect();
+        assert_eq!(codes, vec!["AB", "BC", "MB", "NB", "NL", "NS", "ON", "QC", "SK"]);
+
+        let ontario = registry.get_province_config("ON").unwrap();
+        assert!(ontario.substantially_similar_to_pipeda);
+        assert!(ontario.health_privacy_act.contains("PHIPA"));
+        assert!(!registry.get_province_config("AB").unwrap().substantially_similar_to_pipeda);
+        assert!(matches!(
+            registry.get_province_config("YT"),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_governing_privacy_law() {
+        let mut registry = CanadianProvinceRegistry::new();
+        assert_eq!(
+            registry.governing_privacy_law("ON"),
+            "Personal Health Information Protection Act, 2004 (PHIPA)"
+        );
+        // Territories without their own act fall back to PIPEDA
+        assert_eq!(registry.governing_privacy_law("YT"), "PIPEDA");
+
+        registry.register_province(ProvincialConfig {
+            province_code: "YT".to_string(),
+            province_name: "Yukon".to_string(),
+            health_privacy_act: "Health Information Privacy and Management Act (HIPMA)".to_string(),
+            commissioner: "Yukon Information and Privacy Commissioner".to_string(),
+            substantially_similar_to_pipeda: false,
+            breach_notification_to_commissioner: true,
+            record_retention_years: 10,
+        });
+        assert_eq!(
+            registry.governing_privacy_law("YT"),
+            "Health Information Privacy and Management Act (HIPMA)"
+        );
+        assert_eq!(registry.list_provinces().len(), 10);
+    }
+}
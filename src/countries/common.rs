@@ -86,10 +86,60 @@ pub struct PrescriptionMonitoring {
     pub controlled_substances_only: bool,
 }
 
+/// Organization (hospital) configuration layered on top of the inherited
+/// country/state configuration. Unset fields inherit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrganizationConfig {
+    pub organization_id: String,
+    pub organization_name: String,
+    /// State key of the configuration this organization inherits
+    pub state_key: String,
+    pub additional_regulations: Vec<String>,
+    pub additional_privacy_regulations: Vec<String>,
+    pub data_retention_years: Option<u32>,
+    pub audit_requirements: Option<AuditRequirements>,
+    pub encryption_required: Option<bool>,
+    pub telemedicine_rules: Option<TelemedicineRegulations>,
+    pub prescription_monitoring: Option<PrescriptionMonitoring>,
+    pub conflict_resolution: ConflictResolution,
+}
+
+/// How an organization override that is looser than the inherited rule is handled
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum ConflictResolution {
+    /// Keep the stricter inherited value and report the conflict
+    #[default]
+    Strictest,
+    /// Refuse to build the effective configuration
+    Reject,
+}
+
+/// An organization override that tried to relax an inherited requirement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigConflict {
+    pub field: String,
+    pub inherited: String,
+    pub requested: String,
+    pub resolved: String,
+}
+
+/// Merged configuration an organization operates under
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectiveConfig {
+    pub organization_id: String,
+    pub state_key: String,
+    pub base: BaseHealthcareConfig,
+    pub telemedicine_rules: TelemedicineRegulations,
+    pub prescription_monitoring: PrescriptionMonitoring,
+    /// Overrides that were overruled by stricter inherited rules
+    pub conflicts: Vec<ConfigConflict>,
+}
+
 /// Inheritable configuration manager
 pub struct InheritableConfigManager {
     federal_configs: HashMap<String, FederalConfig>,
     state_configs: HashMap<String, InheritableStateConfig>,
+    organization_configs: HashMap<String, OrganizationConfig>,
     inheritance_cache: HashMap<String, BaseHealthcareConfig>,
 }
 
@@ -98,6 +148,7 @@ impl InheritableConfigManager {
         Self {
             federal_configs: HashMap::new(),
             state_configs: HashMap::new(),
+            organization_configs: HashMap::new(),
             inheritance_cache: HashMap::new(),
         }
     }
@@ -112,6 +163,121 @@ impl InheritableConfigManager {
         self.state_configs.insert(state_key, config);
     }
 
+    /// Register an organization's overrides
+    pub fn register_organization_config(&mut self, config: OrganizationConfig) {
+        self.organization_configs.insert(config.organization_id.clone(), config);
+    }
+
+    /// Get effective configuration for an organization: the state's
+    /// effective configuration with the organization's overrides merged in.
+    /// Organizations may only tighten inherited requirements; looser values
+    /// are resolved according to the organization's `ConflictResolution`.
+    pub fn get_effective_organization_config(&mut self, organization_id: &str) -> Result<EffectiveConfig, HimsError> {
+        let org = self.organization_configs.get(organization_id).cloned()
+            .ok_or_else(|| HimsError::ConfigurationError {
+                message: format!("Organization configuration not found: {}", organization_id),
+            })?;
+
+        let mut base = self.get_effective_config(&org.state_key)?;
+        let state_requirements = &self.state_configs.get(&org.state_key)
+            .ok_or_else(|| HimsError::ConfigurationError {
+                message: format!("State configuration not found: {}", org.state_key),
+            })?
+            .state_specific_requirements;
+        let mut telemedicine = state_requirements.telemedicine_rules.clone();
+        let mut monitoring = state_requirements.prescription_monitoring.clone();
+        let mut conflicts = Vec::new();
+
+        merge_regulations(&mut base.base_regulations, &org.additional_regulations);
+        merge_regulations(&mut base.privacy_regulations, &org.additional_privacy_regulations);
+
+        if let Some(years) = org.data_retention_years {
+            base.data_retention_years = stricter(
+                &mut conflicts, "data_retention_years", base.data_retention_years, years, years >= base.data_retention_years,
+            );
+        }
+        if let Some(required) = org.encryption_required {
+            base.encryption_required = stricter(
+                &mut conflicts, "encryption_required", base.encryption_required, required, required || !base.encryption_required,
+            );
+        }
+        if let Some(audit) = &org.audit_requirements {
+            let inherited = base.audit_requirements.clone();
+            base.audit_requirements = AuditRequirements {
+                retention_period_years: stricter(
+                    &mut conflicts, "audit_requirements.retention_period_years",
+                    inherited.retention_period_years, audit.retention_period_years,
+                    audit.retention_period_years >= inherited.retention_period_years,
+                ),
+                real_time_monitoring: stricter(
+                    &mut conflicts, "audit_requirements.real_time_monitoring",
+                    inherited.real_time_monitoring, audit.real_time_monitoring,
+                    audit.real_time_monitoring || !inherited.real_time_monitoring,
+                ),
+                third_party_audit_required: stricter(
+                    &mut conflicts, "audit_requirements.third_party_audit_required",
+                    inherited.third_party_audit_required, audit.third_party_audit_required,
+                    audit.third_party_audit_required || !inherited.third_party_audit_required,
+                ),
+                log_encryption_required: stricter(
+                    &mut conflicts, "audit_requirements.log_encryption_required",
+                    inherited.log_encryption_required, audit.log_encryption_required,
+                    audit.log_encryption_required || !inherited.log_encryption_required,
+                ),
+            };
+        }
+        if let Some(rules) = &org.telemedicine_rules {
+            telemedicine.allowed = stricter(
+                &mut conflicts, "telemedicine_rules.allowed",
+                telemedicine.allowed, rules.allowed, !rules.allowed || telemedicine.allowed,
+            );
+            telemedicine.cross_state_practice = stricter(
+                &mut conflicts, "telemedicine_rules.cross_state_practice",
+                telemedicine.cross_state_practice, rules.cross_state_practice,
+                !rules.cross_state_practice || telemedicine.cross_state_practice,
+            );
+            merge_regulations(&mut telemedicine.prescription_restrictions, &rules.prescription_restrictions);
+            merge_regulations(&mut telemedicine.required_standards, &rules.required_standards);
+        }
+        if let Some(pdmp) = &org.prescription_monitoring {
+            monitoring.pdmp_required = stricter(
+                &mut conflicts, "prescription_monitoring.pdmp_required",
+                monitoring.pdmp_required, pdmp.pdmp_required, pdmp.pdmp_required || !monitoring.pdmp_required,
+            );
+            monitoring.reporting_timeframe_hours = stricter(
+                &mut conflicts, "prescription_monitoring.reporting_timeframe_hours",
+                monitoring.reporting_timeframe_hours, pdmp.reporting_timeframe_hours,
+                pdmp.reporting_timeframe_hours <= monitoring.reporting_timeframe_hours,
+            );
+            // Monitoring every prescription is stricter than controlled substances only
+            monitoring.controlled_substances_only = stricter(
+                &mut conflicts, "prescription_monitoring.controlled_substances_only",
+                monitoring.controlled_substances_only, pdmp.controlled_substances_only,
+                !pdmp.controlled_substances_only || monitoring.controlled_substances_only,
+            );
+        }
+
+        if org.conflict_resolution == ConflictResolution::Reject && !conflicts.is_empty() {
+            let fields: Vec<&str> = conflicts.iter().map(|c| c.field.as_str()).collect();
+            return Err(HimsError::ConfigurationError {
+                message: format!(
+                    "Organization {} overrides relax inherited requirements: {}",
+                    organization_id,
+                    fields.join(", ")
+                ),
+            });
+        }
+
+        Ok(EffectiveConfig {
+            organization_id: org.organization_id,
+            state_key: org.state_key,
+            base,
+            telemedicine_rules: telemedicine,
+            prescription_monitoring: monitoring,
+            conflicts,
+        })
+    }
+
     /// Get effective configuration for a state (with inheritance resolved)
     pub fn get_effective_config(&mut self, state_key: &str) -> Result<BaseHealthcareConfig, HimsError> {
         // Check cache first
@@ -184,8 +350,8 @@ impl InheritableConfigManager {
     }
 
     /// Validate compliance across inheritance chain
-    pub fn validate_compliance_chain(&mut self, state_key: &str, operation: &str) -> Result<Vec<ComplianceCheck>, HimsError> {
-        let effective_config = self.get_effective_config(state_key)?;
+    pub fn validate_compliance_chain(&mut self, state_key: &str, _operation: &str) -> Result<Vec<ComplianceCheck>, HimsError> {
+        let _effective_config = self.get_effective_config(state_key)?;
         let state_config = self.state_configs.get(state_key).unwrap();
 
         let mut compliance_checks = Vec::new();
@@ -212,6 +378,36 @@ impl InheritableConfigManager {
     }
 }
 
+/// Add regulations not already present, keeping inherited order
+fn merge_regulations(target: &mut Vec<String>, additional: &[String]) {
+    for regulation in additional {
+        if !target.contains(regulation) {
+            target.push(regulation.clone());
+        }
+    }
+}
+
+/// Take the requested value when it is at least as strict as the inherited
+/// one; otherwise keep the inherited value and record the conflict
+fn stricter<T: Clone + std::fmt::Debug>(
+    conflicts: &mut Vec<ConfigConflict>,
+    field: &str,
+    inherited: T,
+    requested: T,
+    requested_is_stricter: bool,
+) -> T {
+    if requested_is_stricter {
+        return requested;
+    }
+    conflicts.push(ConfigConflict {
+        field: field.to_string(),
+        inherited: format!("{:?}", inherited),
+        requested: format!("{:?}", requested),
+        resolved: format!("{:?}", inherited),
+    });
+    inherited
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComplianceCheck {
     pub level: String,
@@ -245,4 +441,184 @@ impl ConfigInheritanceBuilder for ConfigInheritance {
     fn create_custom(base_config: BaseHealthcareConfig) -> ConfigInheritance {
         ConfigInheritance::Custom(base_config)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn federal_base() -> BaseHealthcareConfig {
+        BaseHealthcareConfig {
+            base_regulations: vec!["HIPAA".to_string()],
+            audit_requirements: AuditRequirements {
+                retention_period_years: 6,
+                real_time_monitoring: true,
+                third_party_audit_required: false,
+                log_encryption_required: true,
+            },
+            privacy_regulations: vec!["HIPAA Privacy Rule".to_string()],
+            data_retention_years: 6,
+            encryption_required: true,
+        }
+    }
+
+    fn state() -> InheritableStateConfig {
+        InheritableStateConfig {
+            state_code: "ZZ".to_string(),
+            state_name: "Test State".to_string(),
+            inherits_from: ConfigInheritance::Custom(federal_base()),
+            additional_regulations: vec!["State Medical Privacy Act".to_string()],
+            overrides: Some(ConfigOverrides {
+                audit_requirements: None,
+                data_retention_years: Some(7),
+                additional_privacy_regulations: vec![],
+                stricter_encryption: None,
+            }),
+            state_specific_requirements: StateSpecificRequirements {
+                licensing_authority: "State Medical Board".to_string(),
+                professional_licenses_required: vec![],
+                telemedicine_rules: TelemedicineRegulations {
+                    allowed: true,
+                    cross_state_practice: true,
+                    prescription_restrictions: vec!["No Schedule II".to_string()],
+                    required_standards: vec![],
+                },
+                prescription_monitoring: PrescriptionMonitoring {
+                    pdmp_required: true,
+                    reporting_timeframe_hours: 24,
+                    controlled_substances_only: true,
+                },
+            },
+        }
+    }
+
+    /// A hospital with no overrides of its own
+    fn organization() -> OrganizationConfig {
+        OrganizationConfig {
+            organization_id: "general-hospital".to_string(),
+            organization_name: "General Hospital".to_string(),
+            state_key: "US-ZZ".to_string(),
+            additional_regulations: vec![],
+            additional_privacy_regulations: vec![],
+            data_retention_years: None,
+            audit_requirements: None,
+            encryption_required: None,
+            telemedicine_rules: None,
+            prescription_monitoring: None,
+            conflict_resolution: ConflictResolution::Strictest,
+        }
+    }
+
+    fn manager(organization: OrganizationConfig) -> InheritableConfigManager {
+        let mut manager = InheritableConfigManager::new();
+        manager.register_state_config("US-ZZ".to_string(), state());
+        manager.register_organization_config(organization);
+        manager
+    }
+
+    #[test]
+    fn test_state_inheritance() {
+        let mut manager = manager(organization());
+        let mut child = state();
+        child.inherits_from = ConfigInheritance::State("US-ZZ".to_string());
+        child.overrides = None;
+        manager.register_state_config("US-ZY".to_string(), child);
+
+        let effective = manager.get_effective_config("US-ZZ").unwrap();
+        assert_eq!(effective.base_regulations, vec!["HIPAA", "State Medical Privacy Act"]);
+        assert_eq!(effective.data_retention_years, 7);
+        // A child state inherits its parent's source, not its overrides
+        assert_eq!(manager.get_effective_config("US-ZY").unwrap().data_retention_years, 6);
+        assert!(manager.get_effective_config("US-ZX").is_err());
+    }
+
+    #[test]
+    fn test_organization_without_overrides() {
+        let mut manager = manager(organization());
+        let effective = manager.get_effective_organization_config("general-hospital").unwrap();
+        assert_eq!(effective.state_key, "US-ZZ");
+        assert_eq!(effective.base.data_retention_years, 7);
+        assert!(effective.telemedicine_rules.cross_state_practice);
+        assert_eq!(effective.prescription_monitoring.reporting_timeframe_hours, 24);
+        assert!(effective.conflicts.is_empty());
+
+        assert!(matches!(
+            manager.get_effective_organization_config("unknown"),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+        let mut orphan = organization();
+        orphan.organization_id = "orphan".to_string();
+        orphan.state_key = "US-ZX".to_string();
+        manager.register_organization_config(orphan);
+        assert!(manager.get_effective_organization_config("orphan").is_err());
+    }
+
+    #[test]
+    fn test_stricter_overrides_apply() {
+        let mut organization = organization();
+        organization.additional_regulations = vec!["HIPAA".to_string(), "Joint Commission".to_string()];
+        organization.data_retention_years = Some(25);
+        organization.audit_requirements = Some(AuditRequirements {
+            retention_period_years: 10,
+            real_time_monitoring: true,
+            third_party_audit_required: true,
+            log_encryption_required: true,
+        });
+        organization.telemedicine_rules = Some(TelemedicineRegulations {
+            allowed: true,
+            cross_state_practice: false,
+            prescription_restrictions: vec!["No Schedule II".to_string(), "No benzodiazepines".to_string()],
+            required_standards: vec!["HITRUST".to_string()],
+        });
+        organization.prescription_monitoring = Some(PrescriptionMonitoring {
+            pdmp_required: true,
+            reporting_timeframe_hours: 12,
+            controlled_substances_only: false,
+        });
+
+        let effective = manager(organization).get_effective_organization_config("general-hospital").unwrap();
+        assert!(effective.conflicts.is_empty());
+        assert_eq!(effective.base.base_regulations, vec!["HIPAA", "State Medical Privacy Act", "Joint Commission"]);
+        assert_eq!(effective.base.data_retention_years, 25);
+        assert_eq!(effective.base.audit_requirements.retention_period_years, 10);
+        assert!(effective.base.audit_requirements.third_party_audit_required);
+        assert!(!effective.telemedicine_rules.cross_state_practice);
+        assert_eq!(
+            effective.telemedicine_rules.prescription_restrictions,
+            vec!["No Schedule II", "No benzodiazepines"]
+        );
+        assert_eq!(effective.prescription_monitoring.reporting_timeframe_hours, 12);
+        assert!(!effective.prescription_monitoring.controlled_substances_only);
+    }
+
+    #[test]
+    fn test_looser_overrides_conflict() {
+        let mut organization = organization();
+        organization.data_retention_years = Some(3);
+        organization.encryption_required = Some(false);
+        organization.prescription_monitoring = Some(PrescriptionMonitoring {
+            pdmp_required: true,
+            reporting_timeframe_hours: 72,
+            controlled_substances_only: true,
+        });
+
+        // Strictest keeps the inherited values and reports each conflict
+        let effective = manager(organization.clone()).get_effective_organization_config("general-hospital").unwrap();
+        assert_eq!(effective.base.data_retention_years, 7);
+        assert!(effective.base.encryption_required);
+        assert_eq!(effective.prescription_monitoring.reporting_timeframe_hours, 24);
+        let fields: Vec<&str> = effective.conflicts.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["data_retention_years", "encryption_required", "prescription_monitoring.reporting_timeframe_hours"]
+        );
+        assert_eq!(effective.conflicts[0].inherited, "7");
+        assert_eq!(effective.conflicts[0].requested, "3");
+        assert_eq!(effective.conflicts[0].resolved, "7");
+
+        organization.conflict_resolution = ConflictResolution::Reject;
+        match manager(organization).get_effective_organization_config("general-hospital") {
+            Err(HimsError::ConfigurationError { message }) => assert!(message.contains("encryption_required")),
+            other => panic!("expected a configuration error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::countries::CountryConfig;
+
+/// Area of a regulation a requirement belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementCategory {
+    Privacy,
+    Security,
+    Audit,
+    Consent,
+    BreachNotification,
+    DataResidency,
+    Interoperability,
+    RecordIntegrity,
+}
+
+/// Machine-checkable condition a deployment must meet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RequirementPredicate {
+    EncryptionAtRest,
+    EncryptionInTransit,
+    MultiFactorAuthentication,
+    AuditRetentionYears { min_years: u32 },
+    RealTimeAuditMonitoring,
+    ThirdPartyAudit,
+    TamperEvidentAuditLog,
+    ConsentManagement,
+    BreachNotificationWithinHours { max_hours: u32 },
+    /// Data must be stored in the given country
+    DataResidentIn { country_code: String },
+    StandardSupported { standard: String },
+    AllOf { predicates: Vec<RequirementPredicate> },
+    AnyOf { predicates: Vec<RequirementPredicate> },
+    /// Cannot be checked from configuration; always needs evidence review
+    Attestation,
+}
+
+/// A single requirement of a regulation or standard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceRequirement {
+    /// Stable identifier, e.g. `HIPAA-164.312(a)(2)(iv)`
+    pub id: String,
+    pub standard: String,
+    pub category: RequirementCategory,
+    pub description: String,
+    pub predicate: RequirementPredicate,
+    /// Documents an assessor needs to sign the requirement off
+    pub evidence: Vec<String>,
+}
+
+/// Controls a deployment actually has in place
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeploymentProfile {
+    pub encryption_at_rest: bool,
+    pub encryption_in_transit: bool,
+    pub mfa_enforced: bool,
+    pub audit_retention_years: u32,
+    pub real_time_audit_monitoring: bool,
+    pub third_party_audit: bool,
+    pub tamper_evident_audit_log: bool,
+    pub consent_management: bool,
+    /// Internal commitment for notifying regulators of a breach
+    pub breach_notification_hours: Option<u32>,
+    /// Country the primary data store is located in
+    pub data_country: Option<String>,
+    pub supported_standards: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementStatus {
+    Satisfied,
+    Gap,
+    /// Configuration cannot prove it; needs evidence review
+    NeedsEvidence,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequirementResult {
+    pub requirement_id: String,
+    pub standard: String,
+    pub category: RequirementCategory,
+    pub status: RequirementStatus,
+    pub finding: String,
+    pub evidence: Vec<String>,
+}
+
+/// Gap analysis of one deployment against one country's requirements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GapAnalysisReport {
+    pub country_code: String,
+    pub generated_at: DateTime<Utc>,
+    pub results: Vec<RequirementResult>,
+}
+
+impl GapAnalysisReport {
+    /// No requirement is failing; attestations may still be outstanding
+    pub fn compliant(&self) -> bool {
+        self.results.iter().all(|result| result.status != RequirementStatus::Gap)
+    }
+
+    pub fn gaps(&self) -> Vec<&RequirementResult> {
+        self.results_with(RequirementStatus::Gap)
+    }
+
+    pub fn needs_evidence(&self) -> Vec<&RequirementResult> {
+        self.results_with(RequirementStatus::NeedsEvidence)
+    }
+
+    fn results_with(&self, status: RequirementStatus) -> Vec<&RequirementResult> {
+        self.results.iter().filter(|result| result.status == status).collect()
+    }
+}
+
+/// Evaluates deployments against structured country requirements
+pub struct ComplianceEvaluator;
+
+impl ComplianceEvaluator {
+    /// Evaluate a deployment against the country's regulations plus the
+    /// requirements implied by its audit and localization settings
+    pub fn evaluate(country: &CountryConfig, deployment: &DeploymentProfile) -> GapAnalysisReport {
+        let results = Self::country_requirements(country)
+            .iter()
+            .map(|requirement| Self::evaluate_requirement(requirement, deployment))
+            .collect();
+
+        GapAnalysisReport {
+            country_code: country.country_code.clone(),
+            generated_at: Utc::now(),
+            results,
+        }
+    }
+
+    /// Every requirement that applies in the country
+    pub fn country_requirements(country: &CountryConfig) -> Vec<ComplianceRequirement> {
+        let framework = &country.regulatory_framework;
+        let audit = &framework.audit_requirements;
+        let code = &country.country_code;
+
+        let mut requirements = framework.requirements.clone();
+        requirements.push(requirement(
+            &format!("{}-AUDIT-RETENTION", code),
+            &framework.primary_authority,
+            RequirementCategory::Audit,
+            &format!("Audit records retained for at least {} years", audit.retention_period_years),
+            RequirementPredicate::AuditRetentionYears { min_years: audit.retention_period_years },
+            &["Log retention policy"],
+        ));
+        if audit.real_time_monitoring {
+            requirements.push(requirement(
+                &format!("{}-AUDIT-MONITORING", code),
+                &framework.primary_authority,
+                RequirementCategory::Audit,
+                "Audit events monitored in real time",
+                RequirementPredicate::RealTimeAuditMonitoring,
+                &["Alerting runbook", "Monitoring dashboard export"],
+            ));
+        }
+        if audit.third_party_audit_required {
+            requirements.push(requirement(
+                &format!("{}-AUDIT-THIRD-PARTY", code),
+                &framework.primary_authority,
+                RequirementCategory::Audit,
+                "Independent third-party audit",
+                RequirementPredicate::ThirdPartyAudit,
+                &["Most recent external audit report"],
+            ));
+        }
+        if country.data_localization_required {
+            requirements.push(requirement(
+                &format!("{}-DATA-RESIDENCY", code),
+                &framework.primary_authority,
+                RequirementCategory::DataResidency,
+                &format!("Health data stored within {}", country.country_name),
+                RequirementPredicate::DataResidentIn { country_code: code.clone() },
+                &["Hosting region configuration", "Sub-processor location list"],
+            ));
+        }
+        requirements
+    }
+
+    pub fn evaluate_requirement(requirement: &ComplianceRequirement, deployment: &DeploymentProfile) -> RequirementResult {
+        let status = Self::check(&requirement.predicate, deployment);
+        let finding = match status {
+            RequirementStatus::Satisfied => format!("{}: satisfied", requirement.description),
+            RequirementStatus::Gap => format!("{}: not met by deployment configuration", requirement.description),
+            RequirementStatus::NeedsEvidence => format!("{}: requires evidence review", requirement.description),
+        };
+
+        RequirementResult {
+            requirement_id: requirement.id.clone(),
+            standard: requirement.standard.clone(),
+            category: requirement.category,
+            status,
+            finding,
+            evidence: requirement.evidence.clone(),
+        }
+    }
+
+    fn check(predicate: &RequirementPredicate, deployment: &DeploymentProfile) -> RequirementStatus {
+        let met = |ok: bool| if ok { RequirementStatus::Satisfied } else { RequirementStatus::Gap };
+
+        match predicate {
+            RequirementPredicate::EncryptionAtRest => met(deployment.encryption_at_rest),
+            RequirementPredicate::EncryptionInTransit => met(deployment.encryption_in_transit),
+            RequirementPredicate::MultiFactorAuthentication => met(deployment.mfa_enforced),
+            RequirementPredicate::AuditRetentionYears { min_years } => met(deployment.audit_retention_years >= *min_years),
+            RequirementPredicate::RealTimeAuditMonitoring => met(deployment.real_time_audit_monitoring),
+            RequirementPredicate::ThirdPartyAudit => met(deployment.third_party_audit),
+            RequirementPredicate::TamperEvidentAuditLog => met(deployment.tamper_evident_audit_log),
+            RequirementPredicate::ConsentManagement => met(deployment.consent_management),
+            RequirementPredicate::BreachNotificationWithinHours { max_hours } => {
+                met(deployment.breach_notification_hours.map(|hours| hours <= *max_hours).unwrap_or(false))
+            }
+            RequirementPredicate::DataResidentIn { country_code } => {
+                met(deployment.data_country.as_deref() == Some(country_code.as_str()))
+            }
+            RequirementPredicate::StandardSupported { standard } => {
+                met(deployment.supported_standards.iter().any(|supported| supported == standard))
+            }
+            RequirementPredicate::AllOf { predicates } => {
+                let statuses: Vec<RequirementStatus> = predicates.iter().map(|p| Self::check(p, deployment)).collect();
+                if statuses.contains(&RequirementStatus::Gap) {
+                    RequirementStatus::Gap
+                } else if statuses.contains(&RequirementStatus::NeedsEvidence) {
+                    RequirementStatus::NeedsEvidence
+                } else {
+                    RequirementStatus::Satisfied
+                }
+            }
+            RequirementPredicate::AnyOf { predicates } => {
+                let statuses: Vec<RequirementStatus> = predicates.iter().map(|p| Self::check(p, deployment)).collect();
+                if statuses.contains(&RequirementStatus::Satisfied) {
+                    RequirementStatus::Satisfied
+                } else if statuses.contains(&RequirementStatus::NeedsEvidence) {
+                    RequirementStatus::NeedsEvidence
+                } else {
+                    RequirementStatus::Gap
+                }
+            }
+            RequirementPredicate::Attestation => RequirementStatus::NeedsEvidence,
+        }
+    }
+}
+
+/// Structured requirements for the named regulations and standards.
+/// Standards without a rule catalogue yet become a single attestation
+/// requirement so they still appear in gap analyses.
+pub fn requirements_for_standards(standards: &[&str]) -> Vec<ComplianceRequirement> {
+    standards.iter().flat_map(|standard| standard_requirements(standard)).collect()
+}
+
+fn standard_requirements(standard: &str) -> Vec<ComplianceRequirement> {
+    use RequirementCategory::*;
+    use RequirementPredicate::*;
+
+    match standard {
+        "HIPAA" => vec![
+            requirement("HIPAA-164.312(a)(2)(iv)", standard, Security, "ePHI encrypted at rest", EncryptionAtRest, &["Encryption key management procedure"]),
+            requirement("HIPAA-164.312(e)(1)", standard, Security, "ePHI protected in transmission", EncryptionInTransit, &["TLS configuration"]),
+            requirement("HIPAA-164.312(b)", standard, Audit, "Audit controls record ePHI activity", TamperEvidentAuditLog, &["Audit log sample"]),
+            requirement("HIPAA-164.308(a)(1)", standard, Security, "Security risk analysis performed", Attestation, &["Risk analysis report"]),
+            requirement("HIPAA-164.502(b)", standard, Privacy, "Minimum necessary use and disclosure", Attestation, &["Minimum necessary policy"]),
+        ],
+        "HITECH" => vec![
+            requirement("HITECH-13402", standard, BreachNotification, "Breach notification within 60 days", BreachNotificationWithinHours { max_hours: 60 * 24 }, &["Breach response plan"]),
+        ],
+        "21 CFR Part 11" => vec![
+            requirement("21CFR11-11.10(e)", standard, RecordIntegrity, "Secure, time-stamped audit trails", TamperEvidentAuditLog, &["Audit trail validation report"]),
+            requirement("21CFR11-11.300", standard, Security, "Controls for electronic signature credentials", MultiFactorAuthentication, &["Signature credential policy"]),
+        ],
+        "GDPR" | "UK GDPR" => vec![
+            requirement(&format!("{}-ART6", prefix(standard)), standard, Privacy, "Lawful basis recorded for each processing purpose", ConsentManagement, &["Record of processing activities"]),
+            requirement(&format!("{}-ART32", prefix(standard)), standard, Security, "Encryption of personal data", AllOf { predicates: vec![EncryptionAtRest, EncryptionInTransit] }, &["Security measures description"]),
+            requirement(&format!("{}-ART33", prefix(standard)), standard, BreachNotification, "Breach notified to the supervisory authority within 72 hours", BreachNotificationWithinHours { max_hours: 72 }, &["Breach response plan"]),
+            requirement(&format!("{}-ART35", prefix(standard)), standard, Privacy, "DPIA for large-scale health data processing", Attestation, &["Data protection impact assessment"]),
+        ],
+        "Digital Personal Data Protection Act 2023" => vec![
+            requirement("DPDP-S6", standard, Consent, "Consent obtained through a notice for each purpose", ConsentManagement, &["Consent notice text", "Consent records"]),
+            requirement("DPDP-S8(5)", standard, Security, "Reasonable security safeguards", AllOf { predicates: vec![EncryptionAtRest, EncryptionInTransit] }, &["Security measures description"]),
+            requirement("DPDP-S8(6)", standard, BreachNotification, "Breach intimated to the Data Protection Board", BreachNotificationWithinHours { max_hours: 72 }, &["Breach response plan"]),
+        ],
+        "ABDM Guidelines" => vec![
+            requirement("ABDM-CONSENT", standard, Consent, "Health records shared only through ABDM consent artefacts", ConsentManagement, &["Consent manager integration certificate"]),
+            requirement("ABDM-FHIR", standard, Interoperability, "Health records exchanged as FHIR R4 bundles", StandardSupported { standard: "FHIR R4".to_string() }, &["ABDM sandbox certification"]),
+        ],
+        "PIPEDA" => vec![
+            requirement("PIPEDA-4.3", standard, Consent, "Knowledge and consent for collection, use and disclosure", ConsentManagement, &["Consent records"]),
+            requirement("PIPEDA-4.7", standard, Security, "Safeguards appropriate to the sensitivity of health data", AllOf { predicates: vec![EncryptionAtRest, EncryptionInTransit] }, &["Security measures description"]),
+            requirement("PIPEDA-10.1", standard, BreachNotification, "Breaches of security safeguards reported as soon as feasible", Attestation, &["Breach record log"]),
+        ],
+        "Privacy Act 1988 (Australian Privacy Principles)" => vec![
+            requirement("APP-11", standard, Security, "Personal information protected from misuse and loss", AllOf { predicates: vec![EncryptionAtRest, EncryptionInTransit] }, &["Security measures description"]),
+            requirement("APP-8", standard, DataResidency, "Accountability for cross-border disclosure", Attestation, &["Overseas recipient contracts"]),
+        ],
+        "My Health Records Act 2012" => vec![
+            requirement("MHRA-S77", standard, DataResidency, "My Health Record data held in Australia", DataResidentIn { country_code: "AU".to_string() }, &["Hosting region configuration"]),
+        ],
+        "NHS Data Security and Protection Toolkit (DSPT)" => vec![
+            requirement("DSPT-4", standard, Security, "Multi-factor authentication for remote and privileged access", MultiFactorAuthentication, &["DSPT submission"]),
+            requirement("DSPT-7", standard, Audit, "Access to confidential data monitored", RealTimeAuditMonitoring, &["DSPT submission"]),
+        ],
+        _ => vec![requirement(
+            &format!("{}-ATTESTATION", prefix(standard)),
+            standard,
+            Privacy,
+            &format!("Compliance with {}", standard),
+            Attestation,
+            &["Compliance attestation"],
+        )],
+    }
+}
+
+/// Identifier prefix derived from a standard name
+fn prefix(standard: &str) -> String {
+    standard
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_ascii_uppercase()
+}
+
+fn requirement(
+    id: &str,
+    standard: &str,
+    category: RequirementCategory,
+    description: &str,
+    predicate: RequirementPredicate,
+    evidence: &[&str],
+) -> ComplianceRequirement {
+    ComplianceRequirement {
+        id: id.to_string(),
+        standard: standard.to_string(),
+        category,
+        description: description.to_string(),
+        predicate,
+        evidence: evidence.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::CountryRegistry;
+
+    /// A deployment with every control the catalogue checks in place
+    fn hardened(data_country: &str) -> DeploymentProfile {
+        DeploymentProfile {
+            encryption_at_rest: true,
+            encryption_in_transit: true,
+            mfa_enforced: true,
+            audit_retention_years: 10,
+            real_time_audit_monitoring: true,
+            third_party_audit: true,
+            tamper_evident_audit_log: true,
+            consent_management: true,
+            breach_notification_hours: Some(24),
+            data_country: Some(data_country.to_string()),
+            supported_standards: vec!["FHIR R4".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_requirements_for_standards() {
+        let requirements = requirements_for_standards(&["HIPAA", "UK GDPR", "Local Health Records Act 2001"]);
+        assert_eq!(requirements.len(), 10);
+        assert_eq!(requirements[0].id, "HIPAA-164.312(a)(2)(iv)");
+        assert_eq!(requirements[5].id, "UK-GDPR-ART6");
+
+        // Standards without a catalogue still appear, as an attestation
+        let fallback = requirements.last().unwrap();
+        assert_eq!(fallback.id, "LOCAL-HEALTH-RECORDS-ACT-2001-ATTESTATION");
+        assert_eq!(fallback.predicate, RequirementPredicate::Attestation);
+        assert_eq!(fallback.description, "Compliance with Local Health Records Act 2001");
+    }
+
+    #[test]
+    fn test_predicates() {
+        let deployment = DeploymentProfile {
+            encryption_at_rest: true,
+            breach_notification_hours: Some(96),
+            ..DeploymentProfile::default()
+        };
+        let status = |predicate: RequirementPredicate| ComplianceEvaluator::check(&predicate, &deployment);
+
+        assert_eq!(status(RequirementPredicate::EncryptionAtRest), RequirementStatus::Satisfied);
+        assert_eq!(status(RequirementPredicate::EncryptionInTransit), RequirementStatus::Gap);
+        assert_eq!(
+            status(RequirementPredicate::BreachNotificationWithinHours { max_hours: 72 }),
+            RequirementStatus::Gap
+        );
+        assert_eq!(
+            status(RequirementPredicate::BreachNotificationWithinHours { max_hours: 1440 }),
+            RequirementStatus::Satisfied
+        );
+        assert_eq!(
+            status(RequirementPredicate::DataResidentIn { country_code: "AU".to_string() }),
+            RequirementStatus::Gap
+        );
+        assert_eq!(status(RequirementPredicate::Attestation), RequirementStatus::NeedsEvidence);
+
+        // AllOf takes the worst status, AnyOf the best
+        let mixed = vec![RequirementPredicate::EncryptionAtRest, RequirementPredicate::Attestation];
+        assert_eq!(
+            status(RequirementPredicate::AllOf { predicates: mixed.clone() }),
+            RequirementStatus::NeedsEvidence
+        );
+        assert_eq!(status(RequirementPredicate::AnyOf { predicates: mixed }), RequirementStatus::Satisfied);
+        let failing = vec![RequirementPredicate::EncryptionInTransit, RequirementPredicate::Attestation];
+        assert_eq!(
+            status(RequirementPredicate::AllOf { predicates: failing.clone() }),
+            RequirementStatus::Gap
+        );
+        assert_eq!(status(RequirementPredicate::AnyOf { predicates: failing }), RequirementStatus::NeedsEvidence);
+    }
+
+    #[test]
+    fn test_country_requirements() {
+        let registry = CountryRegistry::new();
+        let australia = registry.get_country_config("AU").unwrap();
+        let ids: Vec<String> = ComplianceEvaluator::country_requirements(australia)
+            .into_iter()
+            .map(|requirement| requirement.id)
+            .collect();
+        assert!(ids.contains(&"AU-AUDIT-RETENTION".to_string()));
+        assert!(ids.contains(&"AU-AUDIT-MONITORING".to_string()));
+        assert!(ids.contains(&"AU-DATA-RESIDENCY".to_string()));
+        assert!(!ids.contains(&"AU-AUDIT-THIRD-PARTY".to_string()));
+
+        let uk = registry.get_country_config("GB").unwrap();
+        let ids: Vec<String> = ComplianceEvaluator::country_requirements(uk)
+            .into_iter()
+            .map(|requirement| requirement.id)
+            .collect();
+        assert!(ids.contains(&"GB-AUDIT-THIRD-PARTY".to_string()));
+        assert!(!ids.contains(&"GB-DATA-RESIDENCY".to_string()));
+    }
+
+    #[test]
+    fn test_gap_analysis() {
+        let registry = CountryRegistry::new();
+        let australia = registry.get_country_config("AU").unwrap();
+
+        let report = ComplianceEvaluator::evaluate(australia, &hardened("AU"));
+        assert_eq!(report.country_code, "AU");
+        assert!(report.compliant());
+        assert!(report.gaps().is_empty());
+        // Attestations remain outstanding even for a compliant deployment
+        assert!(report.needs_evidence().iter().any(|result| result.requirement_id == "APP-8"));
+
+        let mut offshore = hardened("US");
+        offshore.audit_retention_years = 5;
+        let report = ComplianceEvaluator::evaluate(australia, &offshore);
+        assert!(!report.compliant());
+        let gaps: Vec<&str> = report.gaps().iter().map(|result| result.requirement_id.as_str()).collect();
+        assert_eq!(gaps, vec!["MHRA-S77", "AU-AUDIT-RETENTION", "AU-DATA-RESIDENCY"]);
+        let residency = report.gaps()[0];
+        assert_eq!(residency.category, RequirementCategory::DataResidency);
+        assert!(residency.finding.ends_with("not met by deployment configuration"));
+        assert_eq!(residency.evidence, vec!["Hosting region configuration"]);
+    }
+}
@@ -0,0 +1,323 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::HimsError;
+use crate::countries::common::*;
+use crate::security::gdpr_consent::LegalBasis;
+
+/// Hours a controller has to notify the supervisory authority of a breach
+/// (Art. 33)
+pub const BREACH_NOTIFICATION_HOURS: i64 = 72;
+
+/// Countries outside the EEA covered by a Commission adequacy decision
+/// (Art. 45). US recipients are only covered when certified under the
+/// EU-US Data Privacy Framework.
+pub const ADEQUATE_COUNTRIES: &[&str] = &[
+    "AD", "AR", "CA", "CH", "FO", "GB", "GG", "IL", "IM", "JE", "JP", "KR", "NZ", "US", "UY",
+];
+
+/// EEA members; transfers between them are not restricted
+pub const EEA_COUNTRIES: &[&str] = &[
+    "AT", "BE", "BG", "HR", "CY", "CZ", "DK", "EE", "FI", "FR", "DE", "GR", "HU", "IE", "IT", "LV",
+    "LT", "LU", "MT", "NL", "PL", "PT", "RO", "SK", "SI", "ES", "SE", "IS", "LI", "NO",
+];
+
+/// EU-level GDPR configuration inherited by member states
+pub fn get_eu_gdpr_config() -> FederalConfig {
+    FederalConfig {
+        base: BaseHealthcareConfig {
+            base_regulations: vec![
+                "GDPR".to_string(),
+                "NIS2 Directive".to_string(),
+                "Medical Device Regulation (EU) 2017/745".to_string(),
+                "Cross-border Healthcare Directive 2011/24/EU".to_string(),
+            ],
+            audit_requirements: AuditRequirements {
+                retention_period_years: 6,
+                real_time_monitoring: true,
+                third_party_audit_required: false,
+                log_encryption_required: true,
+            },
+            privacy_regulations: vec![
+                "GDPR".to_string(),
+                "ePrivacy Directive".to_string(),
+            ],
+            data_retention_years: 10,
+            encryption_required: true,
+        },
+        federal_authority: "European Data Protection Board".to_string(),
+        interstate_data_sharing_rules: vec![
+            "Free flow of personal data within the EEA".to_string(),
+            "Chapter V safeguards required for transfers outside the EEA".to_string(),
+        ],
+        national_standards: vec![
+            "FHIR R4".to_string(),
+            "SNOMED CT".to_string(),
+            "ICD-10".to_string(),
+            "IHE XDS".to_string(),
+            "EN 13606".to_string(),
+        ],
+    }
+}
+
+/// Art. 9(2) condition lifting the ban on processing health data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpecialCategoryCondition {
+    ExplicitConsent,
+    EmploymentAndSocialSecurity,
+    VitalInterests,
+    NotForProfitBody,
+    MadePublicByDataSubject,
+    LegalClaims,
+    SubstantialPublicInterest,
+    /// Preventive or occupational medicine, diagnosis, care or treatment
+    HealthcareProvision,
+    PublicHealth,
+    ResearchOrStatistics,
+}
+
+/// Legal mechanism for a transfer outside the EEA (Chapter V)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferMechanism {
+    AdequacyDecision,
+    StandardContractualClauses,
+    BindingCorporateRules,
+    /// Art. 49 derogation such as explicit consent for a specific transfer
+    Derogation,
+}
+
+/// A processing operation to be checked against GDPR
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessingActivity {
+    pub purpose: String,
+    pub legal_basis: Option<LegalBasis>,
+    pub special_category_condition: Option<SpecialCategoryCondition>,
+    pub involves_health_data: bool,
+    pub large_scale: bool,
+    /// Two-letter code of the recipient country, if data leaves the controller
+    pub destination_country: Option<String>,
+    pub transfer_mechanism: Option<TransferMechanism>,
+    pub dpia_completed: bool,
+}
+
+/// Organisation facts that decide whether a DPO must be designated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DpoAssessment {
+    pub public_authority: bool,
+    pub core_activity_large_scale_special_categories: bool,
+    pub core_activity_systematic_monitoring: bool,
+    pub staff_processing_personal_data: u32,
+}
+
+/// GDPR rule set, with the member state deviations applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GdprRules {
+    pub breach_notification_hours: i64,
+    /// National threshold of staff regularly processing personal data above
+    /// which a DPO is required regardless of Art. 37(1)
+    pub dpo_staff_threshold: Option<u32>,
+    pub age_of_digital_consent: u8,
+    pub health_data_hosting_certification: Option<String>,
+}
+
+impl Default for GdprRules {
+    fn default() -> Self {
+        Self {
+            breach_notification_hours: BREACH_NOTIFICATION_HOURS,
+            dpo_staff_threshold: None,
+            age_of_digital_consent: 16,
+            health_data_hosting_certification: None,
+        }
+    }
+}
+
+impl GdprRules {
+    /// Check a processing operation and return every rule it breaks
+    pub fn validate_processing(&self, activity: &ProcessingActivity) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        if activity.legal_basis.is_none() {
+            violations.push(format!("No Art. 6 lawful basis for '{}'", activity.purpose));
+        }
+
+        if activity.involves_health_data {
+            if activity.special_category_condition.is_none() {
+                violations.push(format!(
+                    "Health data processed for '{}' without an Art. 9(2) condition",
+                    activity.purpose
+                ));
+            }
+            if activity.special_category_condition == Some(SpecialCategoryCondition::ExplicitConsent)
+                && activity.legal_basis != Some(LegalBasis::Consent)
+            {
+                violations.push("Explicit consent condition requires consent as the lawful basis".to_string());
+            }
+            if activity.large_scale && !activity.dpia_completed {
+                violations.push("Large-scale health data processing requires a DPIA (Art. 35)".to_string());
+            }
+        }
+
+        if let Some(destination) = &activity.destination_country {
+            if let Err(e) = self.validate_transfer(destination, activity.transfer_mechanism) {
+                violations.push(e.to_string());
+            }
+        }
+
+        violations
+    }
+
+    /// Whether a transfer to `destination` is permitted under Chapter V
+    pub fn validate_transfer(&self, destination: &str, mechanism: Option<TransferMechanism>) -> Result<(), HimsError> {
+        let destination = destination.to_ascii_uppercase();
+        if EEA_COUNTRIES.contains(&destination.as_str()) {
+            return Ok(());
+        }
+
+        match mechanism {
+            Some(TransferMechanism::AdequacyDecision) if ADEQUATE_COUNTRIES.contains(&destination.as_str()) => Ok(()),
+            Some(TransferMechanism::AdequacyDecision) => Err(HimsError::ValidationError {
+                message: format!("No adequacy decision covers transfers to {}", destination),
+            }),
+            Some(_) => Ok(()),
+            None => Err(HimsError::ValidationError {
+                message: format!("Transfer to {} requires a Chapter V safeguard", destination),
+            }),
+        }
+    }
+
+    /// Whether the organisation must designate a data protection officer
+    pub fn dpo_required(&self, assessment: &DpoAssessment) -> bool {
+        let article_37 = assessment.public_authority
+            || assessment.core_activity_large_scale_special_categories
+            || assessment.core_activity_systematic_monitoring;
+        let national = self
+            .dpo_staff_threshold
+            .map(|threshold| assessment.staff_processing_personal_data >= threshold)
+            .unwrap_or(false);
+        article_37 || national
+    }
+
+    /// Deadline for notifying the supervisory authority of a breach
+    pub fn breach_notification_deadline(&self, became_aware_at: DateTime<Utc>) -> DateTime<Utc> {
+        became_aware_at + Duration::hours(self.breach_notification_hours)
+    }
+
+    /// Whether a breach notification was made in time; late notifications
+    /// must be accompanied by reasons for the delay
+    pub fn breach_notified_in_time(&self, became_aware_at: DateTime<Utc>, notified_at: DateTime<Utc>) -> bool {
+        notified_at <= self.breach_notification_deadline(became_aware_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Treatment of patients' health data by a hospital, kept in the EEA
+    fn treatment() -> ProcessingActivity {
+        ProcessingActivity {
+            purpose: "treatment".to_string(),
+            legal_basis: Some(LegalBasis::PublicTask),
+            special_category_condition: Some(SpecialCategoryCondition::HealthcareProvision),
+            involves_health_data: true,
+            large_scale: true,
+            destination_country: None,
+            transfer_mechanism: None,
+            dpia_completed: true,
+        }
+    }
+
+    fn dpo_assessment(staff_processing_personal_data: u32) -> DpoAssessment {
+        DpoAssessment {
+            public_authority: false,
+            core_activity_large_scale_special_categories: false,
+            core_activity_systematic_monitoring: false,
+            staff_processing_personal_data,
+        }
+    }
+
+    #[test]
+    fn test_lawful_processing() {
+        let rules = GdprRules::default();
+        assert!(rules.validate_processing(&treatment()).is_empty());
+
+        let mut activity = treatment();
+        activity.legal_basis = None;
+        activity.special_category_condition = None;
+        activity.dpia_completed = false;
+        let violations = rules.validate_processing(&activity);
+        assert_eq!(violations.len(), 3);
+        assert!(violations[0].contains("Art. 6"));
+        assert!(violations[1].contains("Art. 9(2)"));
+        assert!(violations[2].contains("DPIA"));
+
+        // Explicit consent under Art. 9 needs consent under Art. 6 as well
+        let mut activity = treatment();
+        activity.special_category_condition = Some(SpecialCategoryCondition::ExplicitConsent);
+        assert_eq!(rules.validate_processing(&activity).len(), 1);
+        activity.legal_basis = Some(LegalBasis::Consent);
+        assert!(rules.validate_processing(&activity).is_empty());
+
+        // Without health data neither Art. 9 nor a DPIA applies here
+        let mut activity = treatment();
+        activity.involves_health_data = false;
+        activity.special_category_condition = None;
+        activity.dpia_completed = false;
+        assert!(rules.validate_processing(&activity).is_empty());
+    }
+
+    #[test]
+    fn test_cross_border_transfers() {
+        let rules = GdprRules::default();
+        assert!(rules.validate_transfer("de", None).is_ok());
+        assert!(rules.validate_transfer("NO", None).is_ok());
+        assert!(rules.validate_transfer("JP", Some(TransferMechanism::AdequacyDecision)).is_ok());
+        assert!(matches!(
+            rules.validate_transfer("IN", Some(TransferMechanism::AdequacyDecision)),
+            Err(HimsError::ValidationError { .. })
+        ));
+        assert!(rules.validate_transfer("IN", Some(TransferMechanism::StandardContractualClauses)).is_ok());
+        assert!(rules.validate_transfer("IN", Some(TransferMechanism::Derogation)).is_ok());
+        assert!(rules.validate_transfer("US", None).is_err());
+
+        let mut activity = treatment();
+        activity.destination_country = Some("IN".to_string());
+        let violations = rules.validate_processing(&activity);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("Chapter V"));
+    }
+
+    #[test]
+    fn test_dpo_required() {
+        let rules = GdprRules::default();
+        assert!(!rules.dpo_required(&dpo_assessment(500)));
+
+        let mut hospital = dpo_assessment(5);
+        hospital.core_activity_large_scale_special_categories = true;
+        assert!(rules.dpo_required(&hospital));
+        let mut authority = dpo_assessment(0);
+        authority.public_authority = true;
+        assert!(rules.dpo_required(&authority));
+
+        // A national staff threshold applies on top of Art. 37(1)
+        let rules = GdprRules {
+            dpo_staff_threshold: Some(20),
+            ..GdprRules::default()
+        };
+        assert!(!rules.dpo_required(&dpo_assessment(19)));
+        assert!(rules.dpo_required(&dpo_assessment(20)));
+    }
+
+    #[test]
+    fn test_breach_notification() {
+        let rules = GdprRules::default();
+        let became_aware_at = Utc.with_ymd_and_hms(2024, 5, 10, 8, 0, 0).unwrap();
+        let deadline = Utc.with_ymd_and_hms(2024, 5, 13, 8, 0, 0).unwrap();
+        assert_eq!(rules.breach_notification_deadline(became_aware_at), deadline);
+        assert!(rules.breach_notified_in_time(became_aware_at, deadline));
+        assert!(!rules.breach_notified_in_time(became_aware_at, deadline + Duration::seconds(1)));
+    }
+}
@@ -0,0 +1,32 @@
+use crate::countries::eu::gdpr::{GdprRules, BREACH_NOTIFICATION_HOURS};
+use crate::countries::eu::member_states::EuMemberStateConfig;
+
+/// France healthcare configuration on top of GDPR
+pub fn get_france_config() -> EuMemberStateConfig {
+    EuMemberStateConfig {
+        country_code: "FR".to_string(),
+        country_name: "France".to_string(),
+        supervisory_authority: "Commission Nationale de l'Informatique et des Libertés (CNIL)".to_string(),
+        national_regulations: vec![
+            "Loi Informatique et Libertés".to_string(),
+            "Code de la santé publique".to_string(),
+            "Référentiel Hébergeur de Données de Santé (HDS)".to_string(),
+        ],
+        gdpr_rules: GdprRules {
+            breach_notification_hours: BREACH_NOTIFICATION_HOURS,
+            dpo_staff_threshold: None,
+            age_of_digital_consent: 15,
+            // Health data may only be hosted by HDS-certified providers
+            health_data_hosting_certification: Some("HDS".to_string()),
+        },
+        // 20 years from the last stay (Code de la santé publique R1112-7)
+        medical_record_retention_years: 20,
+        supported_standards: vec![
+            "FHIR R4".to_string(),
+            "CI-SIS".to_string(),
+            "INS".to_string(),
+            "CIM-10".to_string(),
+            "CCAM".to_string(),
+        ],
+    }
+}
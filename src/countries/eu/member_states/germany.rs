@@ -0,0 +1,33 @@
+use crate::countries::eu::gdpr::{GdprRules, BREACH_NOTIFICATION_HOURS};
+use crate::countries::eu::member_states::EuMemberStateConfig;
+
+/// Germany healthcare configuration on top of GDPR
+pub fn get_germany_config() -> EuMemberStateConfig {
+    EuMemberStateConfig {
+        country_code: "DE".to_string(),
+        country_name: "Germany".to_string(),
+        supervisory_authority: "Federal Commissioner for Data Protection and Freedom of Information (BfDI) and state DPAs".to_string(),
+        national_regulations: vec![
+            "Bundesdatenschutzgesetz (BDSG)".to_string(),
+            "Patientendaten-Schutz-Gesetz (PDSG)".to_string(),
+            "Sozialgesetzbuch V (SGB V)".to_string(),
+            "Digital-Gesetz (DigiG)".to_string(),
+        ],
+        gdpr_rules: GdprRules {
+            breach_notification_hours: BREACH_NOTIFICATION_HOURS,
+            // BDSG section 38: DPO once 20 people regularly process personal data
+            dpo_staff_threshold: Some(20),
+            age_of_digital_consent: 16,
+            health_data_hosting_certification: Some("BSI C5".to_string()),
+        },
+        // BGB section 630f
+        medical_record_retention_years: 10,
+        supported_standards: vec![
+            "FHIR R4".to_string(),
+            "gematik TI".to_string(),
+            "KBV FHIR profiles".to_string(),
+            "ICD-10-GM".to_string(),
+            "OPS".to_string(),
+        ],
+    }
+}
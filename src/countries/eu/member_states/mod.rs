@@ -0,0 +1,154 @@
+pub mod germany;
+pub mod france;
+pub mod netherlands;
+
+use serde::{Deserialize, Serialize};
+use crate::core::HimsError;
+use crate::countries::{CountryConfig, RegulatoryFramework, AuditRequirements};
+use crate::countries::compliance::requirements_for_standards;
+use crate::countries::eu::gdpr::GdprRules;
+
+/// Member state implementation of GDPR for healthcare
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EuMemberStateConfig {
+    pub country_code: String,
+    pub country_name: String,
+    pub supervisory_authority: String,
+    pub national_regulations: Vec<String>,
+    pub gdpr_rules: GdprRules,
+    pub medical_record_retention_years: u32,
+    pub supported_standards: Vec<String>,
+}
+
+impl EuMemberStateConfig {
+    /// Country configuration for the country registry
+    pub fn to_country_config(&self) -> CountryConfig {
+        let mut standards = vec!["GDPR"];
+        standards.extend(self.national_regulations.iter().map(String::as_str));
+
+        CountryConfig {
+            country_code: self.country_code.clone(),
+            country_name: self.country_name.clone(),
+            regulatory_framework: RegulatoryFramework {
+                primary_authority: self.supervisory_authority.clone(),
+                requirements: requirements_for_standards(&standards),
+                audit_requirements: AuditRequirements {
+                    retention_period_years: self.medical_record_retention_years,
+                    real_time_monitoring: true,
+                    third_party_audit_required: self.gdpr_rules.health_data_hosting_certification.is_some(),
+                },
+            },
+            data_localization_required: false,
+            supported_standards: self.supported_standards.clone(),
+            privacy_regulations: std::iter::once("GDPR".to_string())
+                .chain(self.national_regulations.first().cloned())
+                .collect(),
+        }
+    }
+}
+
+/// Registry of member state GDPR overrides
+pub struct EuMemberStateRegistry {
+    member_states: std::collections::HashMap<String, EuMemberStateConfig>,
+}
+
+impl EuMemberStateRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            member_states: std::collections::HashMap::new(),
+        };
+        registry.initialize_member_states();
+        registry
+    }
+
+    fn initialize_member_states(&mut self) {
+        self.register_member_state(germany::get_germany_config());
+        self.register_member_state(france::get_france_config());
+        self.register_member_state(netherlands::get_netherlands_config());
+    }
+
+    pub fn register_member_state(&mut self, config: EuMemberStateConfig) {
+        self.member_states.insert(config.country_code.clone(), config);
+    }
+
+    pub fn get_member_state_config(&self, country_code: &str) -> Result<&EuMemberStateConfig, HimsError> {
+        self.member_states.get(country_code).ok_or_else(|| HimsError::ConfigurationError {
+            message: format!("EU member state configuration not found for: {}", country_code),
+        })
+    }
+
+    /// GDPR rules for a member state, or the EU baseline when the member
+    /// state has no overrides registered
+    pub fn gdpr_rules(&self, country_code: &str) -> GdprRules {
+        self.member_states
+            .get(country_code)
+            .map(|config| config.gdpr_rules.clone())
+            .unwrap_or_default()
+    }
+
+    /// All registered member states, sorted by code
+    pub fn list_member_states(&self) -> Vec<&EuMemberStateConfig> {
+        let mut member_states: Vec<&EuMemberStateConfig> = self.member_states.values().collect();
+        member_states.sort_by(|a, b| a.country_code.cmp(&b.country_code));
+        member_states
+    }
+}
+
+impl Default for EuMemberStateRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub use germany::*;
+pub use france::*;
+pub use netherlands::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::eu::gdpr::{DpoAssessment, BREACH_NOTIFICATION_HOURS};
+
+    #[test]
+    fn test_member_state_overrides() {
+        let registry = EuMemberStateRegistry::new();
+        let codes: Vec<&str> = registry.list_member_states().iter().map(|m| m.country_code.as_str()).collect();
+        assert_eq!(codes, vec!["DE", "FR", "NL"]);
+
+        let germany = registry.gdpr_rules("DE");
+        assert_eq!(germany.dpo_staff_threshold, Some(20));
+        let clinic = DpoAssessment {
+            public_authority: false,
+            core_activity_large_scale_special_categories: false,
+            core_activity_systematic_monitoring: false,
+            staff_processing_personal_data: 25,
+        };
+        assert!(germany.dpo_required(&clinic));
+        assert!(!registry.gdpr_rules("NL").dpo_required(&clinic));
+
+        let france = registry.gdpr_rules("FR");
+        assert_eq!(france.age_of_digital_consent, 15);
+        assert_eq!(france.health_data_hosting_certification.as_deref(), Some("HDS"));
+
+        // Member states without overrides get the EU baseline
+        let italy = registry.gdpr_rules("IT");
+        assert_eq!(italy.breach_notification_hours, BREACH_NOTIFICATION_HOURS);
+        assert_eq!(italy.age_of_digital_consent, 16);
+        assert_eq!(italy.dpo_staff_threshold, None);
+        assert!(matches!(
+            registry.get_member_state_config("IT"),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_to_country_config() {
+        let registry = EuMemberStateRegistry::new();
+        let config = registry.get_member_state_config("FR").unwrap().to_country_config();
+        assert_eq!(config.country_code, "FR");
+        assert_eq!(config.regulatory_framework.audit_requirements.retention_period_years, 20);
+        assert!(config.regulatory_framework.audit_requirements.third_party_audit_required);
+        assert_eq!(config.privacy_regulations, vec!["GDPR", "Loi Informatique et Libertés"]);
+        assert!(!config.data_localization_required);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::countries::eu::gdpr::{GdprRules, BREACH_NOTIFICATION_HOURS};
+use crate::countries::eu::member_states::EuMemberStateConfig;
+
+/// Netherlands healthcare configuration on top of GDPR
+pub fn get_netherlands_config() -> EuMemberStateConfig {
+    EuMemberStateConfig {
+        country_code: "NL".to_string(),
+        country_name: "Netherlands".to_string(),
+        supervisory_authority: "Autoriteit Persoonsgegevens".to_string(),
+        national_regulations: vec![
+            "Uitvoeringswet AVG (UAVG)".to_string(),
+            "Wet op de geneeskundige behandelingsovereenkomst (WGBO)".to_string(),
+            "Wet aanvullende bepalingen verwerking persoonsgegevens in de zorg (Wabvpz)".to_string(),
+            "NEN 7510".to_string(),
+        ],
+        gdpr_rules: GdprRules {
+            breach_notification_hours: BREACH_NOTIFICATION_HOURS,
+            dpo_staff_threshold: None,
+            age_of_digital_consent: 16,
+            health_data_hosting_certification: Some("NEN 7510".to_string()),
+        },
+        // WGBO: 20 years from the last change to the record
+        medical_record_retention_years: 20,
+        supported_standards: vec![
+            "FHIR R4".to_string(),
+            "zib (Zorginformatiebouwstenen)".to_string(),
+            "HL7v3".to_string(),
+            "SNOMED CT".to_string(),
+        ],
+    }
+}
@@ -0,0 +1,43 @@
+pub mod gdpr;
+pub mod member_states;
+
+use crate::countries::{CountryConfig, RegulatoryFramework, AuditRequirements};
+use crate::countries::compliance::requirements_for_standards;
+
+/// Get European Union configuration
+pub fn get_eu_config() -> CountryConfig {
+    CountryConfig {
+        country_code: "EU".to_string(),
+        country_name: "European Union".to_string(),
+        regulatory_framework: RegulatoryFramework {
+            primary_authority: "European Data Protection Board".to_string(),
+            requirements: requirements_for_standards(&[
+                "GDPR",
+                "NIS2 Directive",
+                "Medical Device Regulation (EU) 2017/745",
+                "European Health Data Space Regulation",
+            ]),
+            audit_requirements: AuditRequirements {
+                retention_period_years: 6,
+                real_time_monitoring: true,
+                third_party_audit_required: false,
+            },
+        },
+        data_localization_required: false,
+        supported_standards: vec![
+            "FHIR R4".to_string(),
+            "HL7v2".to_string(),
+            "SNOMED CT".to_string(),
+            "ICD-10".to_string(),
+            "IHE XDS".to_string(),
+            "EN 13606".to_string(),
+        ],
+        privacy_regulations: vec![
+            "GDPR".to_string(),
+            "ePrivacy Directive".to_string(),
+        ],
+    }
+}
+
+pub use gdpr::*;
+pub use member_states::*;
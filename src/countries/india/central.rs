@@ -42,12 +42,12 @@ pub fn get_india_central_config() -> FederalConfig {
 pub struct IndiaCentralCompliance;
 
 impl IndiaCentralCompliance {
-    pub fn validate_dpdp_compliance(operation: &str) -> Result<bool, crate::core::HimsError> {
+    pub fn validate_dpdp_compliance(_operation: &str) -> Result<bool, crate::core::HimsError> {
         // Digital Personal Data Protection Act compliance
         Ok(true) // Placeholder
     }
 
-    pub fn validate_abdm_compliance(operation: &str) -> Result<bool, crate::core::HimsError> {
+    pub fn validate_abdm_compliance(_operation: &str) -> Result<bool, crate::core::HimsError> {
         // ABDM compliance validation
         Ok(true) // Placeholder
     }
@@ -1,7 +1,9 @@
 pub mod states;
 pub mod central;
+pub mod verification;
 
 use crate::countries::{CountryConfig, RegulatoryFramework, AuditRequirements};
+use crate::countries::compliance::requirements_for_standards;
 
 /// Get India configuration
 pub fn get_india_config() -> CountryConfig {
@@ -10,12 +12,12 @@ pub fn get_india_config() -> CountryConfig {
         country_name: "India".to_string(),
         regulatory_framework: RegulatoryFramework {
             primary_authority: "Ministry of Health and Family Welfare".to_string(),
-            compliance_standards: vec![
-                "ABDM Guidelines".to_string(),
-                "Digital Personal Data Protection Act 2023".to_string(),
-                "Information Technology Act 2000".to_string(),
-                "Clinical Establishments Act".to_string(),
-            ],
+            requirements: requirements_for_standards(&[
+                "ABDM Guidelines",
+                "Digital Personal Data Protection Act 2023",
+                "Information Technology Act 2000",
+                "Clinical Establishments Act",
+            ]),
             audit_requirements: AuditRequirements {
                 retention_period_years: 5,
                 real_time_monitoring: true,
@@ -38,4 +40,5 @@ pub fn get_india_config() -> CountryConfig {
 }
 
 pub use states::*;
-pub use central::*;
\ No newline at end of file
+pub use central::*;
+pub use verification::*;
\ No newline at end of file
@@ -1,4 +1,4 @@
-// TODO: Implement state modules
+// States and union territories are data-driven; see data/indian_states.json
 // pub mod maharashtra;
 // pub mod karnataka;
 // pub mod tamil_nadu;
@@ -13,18 +13,41 @@
 use serde::{Deserialize, Serialize};
 use crate::core::HimsError;
 
+/// Embedded definitions for the states and union territories
+const INDIAN_STATES_JSON: &str = include_str!("data/indian_states.json");
+
 /// Indian state-specific healthcare configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndianStateConfig {
     pub state_code: String,
     pub state_name: String,
+    #[serde(default)]
+    pub jurisdiction: IndianJurisdiction,
     pub local_regulations: Vec<String>,
     pub state_health_authority: String,
+    #[serde(default)]
+    pub health_authority_contact: Option<HealthAuthorityContact>,
     pub abdm_integration_status: AbdmIntegrationStatus,
     pub ayush_regulations: AyushRegulations,
     pub tribal_healthcare_provisions: bool,
 }
 
+/// Kind of Indian jurisdiction a configuration applies to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IndianJurisdiction {
+    #[default]
+    State,
+    UnionTerritory,
+}
+
+/// Public contact points of a state health authority
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthAuthorityContact {
+    pub website: Option<String>,
+    pub helpline: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AbdmIntegrationStatus {
     pub hip_enabled: bool,
@@ -40,6 +63,9 @@ pub struct AyushRegulations {
     pub unani_licensed: bool,
     pub siddha_licensed: bool,
     pub homeopathy_licensed: bool,
+    /// Body registering AYUSH practitioners in the state
+    #[serde(default)]
+    pub licensing_authority: Option<String>,
 }
 
 pub struct IndianStateRegistry {
@@ -56,11 +82,22 @@ impl IndianStateRegistry {
     }
 
     fn initialize_states(&mut self) {
-        // TODO: Register states as they are implemented
-        // self.register_state(maharashtra::get_maharashtra_config());
-        // self.register_state(karnataka::get_karnataka_config());
-        // self.register_state(tamil_nadu::get_tamil_nadu_config());
-        // self.register_state(kerala::get_kerala_config());
+        let states = load_indian_states_from_json(INDIAN_STATES_JSON)
+            .expect("embedded Indian state definitions are valid");
+        for config in states {
+            self.register_state(config);
+        }
+    }
+
+    /// Register additional or replacement definitions, e.g. from a
+    /// deployment-specific JSON file
+    pub fn load_from_json(&mut self, json: &str) -> Result<usize, HimsError> {
+        let states = load_indian_states_from_json(json)?;
+        let count = states.len();
+        for config in states {
+            self.register_state(config);
+        }
+        Ok(count)
     }
 
     pub fn register_state(&mut self, config: IndianStateConfig) {
@@ -72,6 +109,53 @@ impl IndianStateRegistry {
             message: format!("Indian state configuration not found for: {}", state_code),
         })
     }
+
+    /// All registered states and union territories, sorted by code
+    pub fn list_states(&self) -> Vec<&IndianStateConfig> {
+        let mut states: Vec<&IndianStateConfig> = self.states.values().collect();
+        states.sort_by(|a, b| a.state_code.cmp(&b.state_code));
+        states
+    }
+
+    /// Registered states of one jurisdiction kind, sorted by code
+    pub fn list_by_jurisdiction(&self, jurisdiction: IndianJurisdiction) -> Vec<&IndianStateConfig> {
+        self.list_states()
+            .into_iter()
+            .filter(|state| state.jurisdiction == jurisdiction)
+            .collect()
+    }
+
+    /// States whose facilities can link records to ABDM as health
+    /// information providers
+    pub fn abdm_hip_states(&self) -> Vec<&IndianStateConfig> {
+        self.list_states()
+            .into_iter()
+            .filter(|state| state.abdm_integration_status.hip_enabled)
+            .collect()
+    }
+}
+
+/// Parse state definitions in the `IndianStateConfig` JSON shape
+pub fn load_indian_states_from_json(json: &str) -> Result<Vec<IndianStateConfig>, HimsError> {
+    let states: Vec<IndianStateConfig> = serde_json::from_str(json).map_err(|e| HimsError::ConfigurationError {
+        message: format!("Invalid Indian state definitions: {}", e),
+    })?;
+
+    for state in &states {
+        let valid_code = state.state_code.len() == 2 && state.state_code.chars().all(|c| c.is_ascii_uppercase());
+        if !valid_code {
+            return Err(HimsError::ConfigurationError {
+                message: format!("Invalid Indian state code: {}", state.state_code),
+            });
+        }
+        let rate = state.abdm_integration_status.health_id_adoption_rate;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(HimsError::ConfigurationError {
+                message: format!("Invalid ABHA adoption rate for {}: {}", state.state_code, rate),
+            });
+        }
+    }
+    Ok(states)
 }
 
 impl Default for IndianStateRegistry {
@@ -83,4 +167,58 @@ impl Default for IndianStateRegistry {
 // pub use maharashtra::*;
 // pub use karnataka::*;
 // pub use tamil_nadu::*;
-// pub use kerala::*;
\ No newline at end of file
+// pub use kerala::*;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_states() {
+        let registry = IndianStateRegistry::new();
+        assert_eq!(registry.list_states().len(), 36);
+        assert_eq!(registry.list_by_jurisdiction(IndianJurisdiction::State).len(), 28);
+        let territories: Vec<&str> = registry
+            .list_by_jurisdiction(IndianJurisdiction::UnionTerritory)
+            .iter()
+            .map(|state| state.state_code.as_str())
+            .collect();
+        assert_eq!(territories, vec!["AN", "CH", "DH", "DL", "JK", "LA", "LD", "PY"]);
+
+        let maharashtra = registry.get_state_config("MH").unwrap();
+        assert_eq!(maharashtra.state_name, "Maharashtra");
+        assert!(maharashtra.abdm_integration_status.hip_enabled);
+        assert_eq!(
+            maharashtra.ayush_regulations.licensing_authority.as_deref(),
+            Some("Maharashtra Board of Indian Medicine")
+        );
+        let contact = maharashtra.health_authority_contact.as_ref().unwrap();
+        assert_eq!(contact.helpline.as_deref(), Some("104"));
+        assert!(!registry.get_state_config("LA").unwrap().abdm_integration_status.hiu_enabled);
+        assert!(matches!(
+            registry.get_state_config("XX"),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_from_json() {
+        let mut registry = IndianStateRegistry::new();
+        let mut ladakh = registry.get_state_config("LA").unwrap().clone();
+        ladakh.abdm_integration_status.hip_enabled = false;
+        let json = serde_json::to_string(&vec![ladakh.clone()]).unwrap();
+
+        // Replacing a state takes it out of the ABDM provider list
+        assert_eq!(registry.abdm_hip_states().len(), 36);
+        assert_eq!(registry.load_from_json(&json).unwrap(), 1);
+        assert_eq!(registry.list_states().len(), 36);
+        assert_eq!(registry.abdm_hip_states().len(), 35);
+        assert!(registry.abdm_hip_states().iter().all(|state| state.state_code != "LA"));
+
+        ladakh.state_code = "ladakh".to_string();
+        assert!(registry.load_from_json(&serde_json::to_string(&vec![ladakh.clone()]).unwrap()).is_err());
+        ladakh.state_code = "LA".to_string();
+        ladakh.abdm_integration_status.health_id_adoption_rate = 39.0;
+        assert!(registry.load_from_json(&serde_json::to_string(&vec![ladakh]).unwrap()).is_err());
+        assert!(load_indian_states_from_json("[{}]").is_err());
+    }
+}
@@ -0,0 +1,500 @@
+//! Demographic verification against ABDM and UIDAI
+//!
+//! Registration desks confirm that the name, gender and date of birth a
+//! patient gives match their ABHA (through the ABDM sandbox's demographic
+//! authentication) or their Aadhaar (through the UIDAI demographic
+//! authentication an AUA gateway exposes). Aadhaar numbers are never kept:
+//! callers store the masked form and the hash this module derives.
+
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::Gender;
+
+/// Tokens are renewed this long before the gateway says they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Verhoeff multiplication and permutation tables, by which the last digit
+/// of an Aadhaar number checks the rest
+const VERHOEFF_D: [[u8; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+const VERHOEFF_P: [[u8; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+];
+
+/// The ID a patient's demographics are verified against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdentityDocument {
+    Abha,
+    Aadhaar,
+}
+
+impl IdentityDocument {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdentityDocument::Abha => "abha",
+            IdentityDocument::Aadhaar => "aadhaar",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "abha" => Some(IdentityDocument::Abha),
+            "aadhaar" => Some(IdentityDocument::Aadhaar),
+            _ => None,
+        }
+    }
+}
+
+/// Demographics a patient gave at registration, to check against their ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemographicVerificationRequest {
+    pub document: IdentityDocument,
+    /// ABHA number or Aadhaar number, with or without separators
+    pub number: String,
+    pub name: String,
+    pub gender: Gender,
+    pub date_of_birth: Option<NaiveDate>,
+    /// Year of birth, where the patient does not know the date
+    pub year_of_birth: Option<i32>,
+    pub mobile: Option<String>,
+}
+
+impl DemographicVerificationRequest {
+    /// The ID number's digits, checked for its document's length and, for
+    /// Aadhaar, its Verhoeff check digit
+    pub fn digits(&self) -> Result<String, HimsError> {
+        let digits: String = self.number.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+        let valid = digits.chars().all(|c| c.is_ascii_digit())
+            && match self.document {
+                IdentityDocument::Abha => digits.len() == 14,
+                IdentityDocument::Aadhaar => {
+                    digits.len() == 12 && !digits.starts_with(['0', '1']) && verhoeff_valid(&digits)
+                }
+            };
+        if !valid {
+            return Err(HimsError::ValidationError {
+                message: format!("Not a valid {} number", self.document.as_str()),
+            });
+        }
+        Ok(digits)
+    }
+
+    /// Check that the request can be sent: a valid number, a name, a known
+    /// gender and a date or year of birth
+    pub fn validate(&self) -> Result<(), HimsError> {
+        self.digits()?;
+        let invalid = |message: &str| HimsError::ValidationError { message: message.to_string() };
+        if self.name.trim().is_empty() {
+            return Err(invalid("Name is required for demographic verification"));
+        }
+        if gender_code(self.document, &self.gender).is_none() {
+            return Err(invalid("Gender must be known for demographic verification"));
+        }
+        if self.birth_year().is_none() {
+            return Err(invalid("Date or year of birth is required for demographic verification"));
+        }
+        Ok(())
+    }
+
+    /// The number with all but its last four digits hidden, as Aadhaar
+    /// numbers may be displayed and stored
+    pub fn masked_number(&self) -> Result<String, HimsError> {
+        let digits = self.digits()?;
+        let (hidden, shown) = digits.split_at(digits.len() - 4);
+        Ok(format!("{}{}", "X".repeat(hidden.len()), shown))
+    }
+
+    /// SHA-256 of the ID number alone, to find a patient's verifications by
+    /// without keeping the number
+    pub fn number_hash(&self) -> Result<String, HimsError> {
+        let digits = self.digits()?;
+        Ok(hex_digest(&format!("{}|{}", self.document.as_str(), digits)))
+    }
+
+    /// SHA-256 of everything that is verified, so that the same demographics
+    /// for the same number find an earlier answer
+    pub fn cache_key(&self) -> Result<String, HimsError> {
+        let digits = self.digits()?;
+        let name = self.name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let birth = match self.date_of_birth {
+            Some(date) => date.to_string(),
+            None => self.year_of_birth.map(|year| year.to_string()).unwrap_or_default(),
+        };
+        let mobile: String = self.mobile.as_deref().unwrap_or("").chars().filter(char::is_ascii_digit).collect();
+        Ok(hex_digest(&format!(
+            "{}|{}|{}|{}|{}|{}",
+            self.document.as_str(),
+            digits,
+            name,
+            gender_code(self.document, &self.gender).unwrap_or(""),
+            birth,
+            mobile
+        )))
+    }
+
+    fn birth_year(&self) -> Option<i32> {
+        self.date_of_birth.map(|date| date.year()).or(self.year_of_birth)
+    }
+}
+
+/// Whether the ID's holder has the demographics given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationOutcome {
+    Verified,
+    Mismatch,
+}
+
+impl VerificationOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationOutcome::Verified => "verified",
+            VerificationOutcome::Mismatch => "mismatch",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "verified" => Some(VerificationOutcome::Verified),
+            "mismatch" => Some(VerificationOutcome::Mismatch),
+            _ => None,
+        }
+    }
+}
+
+/// What the ID authority answered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub outcome: VerificationOutcome,
+    /// Transaction the authority answered under, for reconciling with its logs
+    pub reference: Option<String>,
+    /// Why the demographics did not match, where the authority says
+    pub detail: Option<String>,
+}
+
+/// An authority patients' demographics are verified with
+#[async_trait]
+pub trait DemographicVerifier: Send + Sync {
+    async fn verify(&self, request: &DemographicVerificationRequest) -> Result<VerificationResult, HimsError>;
+}
+
+/// Endpoints and credentials of the ABDM sandbox, and of the AUA gateway
+/// Aadhaar demographic authentication goes through
+#[derive(Debug, Clone)]
+pub struct AbdmVerificationConfig {
+    /// ABDM gateway sessions are opened with, e.g. `https://dev.abdm.gov.in/gateway`
+    pub gateway_url: String,
+    /// ABHA service, e.g. `https://healthidsbx.abdm.gov.in/api`
+    pub health_id_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Consent manager the client is registered with: `sbx` in the sandbox
+    pub cm_id: String,
+    /// AUA gateway for UIDAI demographic authentication; Aadhaar numbers
+    /// cannot be verified without one
+    pub aadhaar_auth_url: Option<String>,
+    pub aadhaar_auth_key: Option<String>,
+    pub timeout_ms: u64,
+}
+
+impl AbdmVerificationConfig {
+    /// Settings from `ABDM_CLIENT_ID` and `ABDM_CLIENT_SECRET`, with
+    /// `ABDM_GATEWAY_URL`, `ABDM_HEALTH_ID_URL` and `ABDM_CM_ID` defaulting to
+    /// the sandbox, and `UIDAI_AUTH_URL` and `UIDAI_AUTH_KEY` for Aadhaar
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let url = |name: &str, default: &str| {
+            var(name).unwrap_or_else(|| default.to_string()).trim_end_matches('/').to_string()
+        };
+        Some(Self {
+            gateway_url: url("ABDM_GATEWAY_URL", "https://dev.abdm.gov.in/gateway"),
+            health_id_url: url("ABDM_HEALTH_ID_URL", "https://healthidsbx.abdm.gov.in/api"),
+            client_id: var("ABDM_CLIENT_ID")?,
+            client_secret: var("ABDM_CLIENT_SECRET")?,
+            cm_id: var("ABDM_CM_ID").unwrap_or_else(|| "sbx".to_string()),
+            aadhaar_auth_url: var("UIDAI_AUTH_URL"),
+            aadhaar_auth_key: var("UIDAI_AUTH_KEY"),
+            timeout_ms: 10_000,
+        })
+    }
+}
+
+/// ABHA demographic authentication through the ABDM sandbox, and Aadhaar
+/// demographic authentication through an AUA gateway
+pub struct AbdmVerifier {
+    config: AbdmVerificationConfig,
+    http: reqwest::Client,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl AbdmVerifier {
+    pub fn new(config: AbdmVerificationConfig) -> Result<Self, HimsError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| HimsError::ConfigurationError { message: e.to_string() })?;
+        Ok(Self { config, http, token: Mutex::new(None) })
+    }
+
+    /// Gateway session token, reused until shortly before it expires
+    async fn session_token(&self) -> Result<String, HimsError> {
+        let mut cached = self.token.lock().await;
+        if let Some((token, _)) = cached.as_ref().filter(|(_, expires_at)| *expires_at > Instant::now()) {
+            return Ok(token.clone());
+        }
+
+        let response = self
+            .http
+            .post(format!("{}/v0.5/sessions", self.config.gateway_url))
+            .json(&json!({ "clientId": self.config.client_id, "clientSecret": self.config.client_secret }))
+            .send()
+            .await
+            .map_err(|e| network_error("ABDM session", e))?;
+        if !response.status().is_success() {
+            return Err(HimsError::AuthenticationError {
+                message: format!("ABDM gateway refused the session: {}", response.status()),
+            });
+        }
+        let body: Value = response.json().await.map_err(|e| network_error("ABDM session", e))?;
+        let token = body["accessToken"].as_str().ok_or_else(|| HimsError::AuthenticationError {
+            message: "ABDM session response has no accessToken".to_string(),
+        })?;
+        let lifetime = Duration::from_secs(body["expiresIn"].as_u64().unwrap_or(600));
+        *cached = Some((token.to_string(), Instant::now() + lifetime.saturating_sub(TOKEN_EXPIRY_MARGIN)));
+        Ok(token.to_string())
+    }
+
+    /// `auth/init` for demographic authentication, then
+    /// `auth/confirmWithDemographics`, which answers 2xx on a match
+    async fn verify_abha(&self, request: &DemographicVerificationRequest) -> Result<VerificationResult, HimsError> {
+        let token = self.session_token().await?;
+        let digits = request.digits()?;
+        let health_id_number =
+            format!("{}-{}-{}-{}", &digits[0..2], &digits[2..6], &digits[6..10], &digits[10..14]);
+
+        let response = self
+            .http
+            .post(format!("{}/v1/auth/init", self.config.health_id_url))
+            .bearer_auth(&token)
+            .header("X-CM-ID", &self.config.cm_id)
+            .json(&json!({ "authMethod": "DEMOGRAPHICS", "healthid": health_id_number }))
+            .send()
+            .await
+            .map_err(|e| network_error("ABHA auth init", e))?;
+        if response.status().is_client_error() {
+            return Ok(mismatch(None, abdm_error_detail(&response.json().await.unwrap_or(Value::Null))));
+        }
+        let body = success_body("ABHA auth init", response).await?;
+        let txn_id = body["txnId"].as_str().map(str::to_string).ok_or_else(|| HimsError::NetworkError {
+            message: "ABHA auth init response has no txnId".to_string(),
+        })?;
+
+        let mut demographics = json!({
+            "txnId": txn_id,
+            "name": request.name.trim(),
+            "gender": gender_code(request.document, &request.gender),
+            "yearOfBirth": request.birth_year().map(|year| year.to_string()),
+        });
+        if let Some(date) = request.date_of_birth {
+            demographics["dayOfBirth"] = json!(date.day().to_string());
+            demographics["monthOfBirth"] = json!(date.month().to_string());
+        }
+        if let Some(mobile) = &request.mobile {
+            demographics["mobile"] = json!(mobile);
+        }
+        let response = self
+            .http
+            .post(format!("{}/v1/auth/confirmWithDemographics", self.config.health_id_url))
+            .bearer_auth(&token)
+            .header("X-CM-ID", &self.config.cm_id)
+            .json(&demographics)
+            .send()
+            .await
+            .map_err(|e| network_error("ABHA demographic confirmation", e))?;
+        if response.status().is_client_error() {
+            let body = response.json().await.unwrap_or(Value::Null);
+            return Ok(mismatch(Some(txn_id), abdm_error_detail(&body)));
+        }
+        success_body("ABHA demographic confirmation", response).await?;
+        Ok(VerificationResult { outcome: VerificationOutcome::Verified, reference: Some(txn_id), detail: None })
+    }
+
+    /// UIDAI demographic authentication, sent to the AUA gateway as JSON;
+    /// its `ret` is `y` on a match
+    async fn verify_aadhaar(&self, request: &DemographicVerificationRequest) -> Result<VerificationResult, HimsError> {
+        let url = self.config.aadhaar_auth_url.as_deref().ok_or_else(|| HimsError::ConfigurationError {
+            message: "No UIDAI authentication gateway is configured for Aadhaar verification".to_string(),
+        })?;
+        let txn = Uuid::new_v4().to_string();
+        let mut demographics = json!({
+            "name": request.name.trim(),
+            "gender": gender_code(request.document, &request.gender),
+        });
+        match request.date_of_birth {
+            Some(date) => demographics["dob"] = json!(date.format("%Y-%m-%d").to_string()),
+            None => demographics["yob"] = json!(request.year_of_birth.map(|year| year.to_string())),
+        }
+        if let Some(mobile) = &request.mobile {
+            demographics["phone"] = json!(mobile);
+        }
+
+        let mut call = self
+            .http
+            .post(url)
+            .json(&json!({ "uid": request.digits()?, "txn": txn, "consent": "Y", "demo": demographics }));
+        if let Some(key) = &self.config.aadhaar_auth_key {
+            call = call.bearer_auth(key);
+        }
+        let response = call.send().await.map_err(|e| network_error("UIDAI authentication", e))?;
+        let body = success_body("UIDAI authentication", response).await?;
+        let reference = body["txn"].as_str().map(str::to_string).or(Some(txn));
+        match body["ret"].as_str().map(str::to_ascii_lowercase).as_deref() {
+            Some("y") => Ok(VerificationResult { outcome: VerificationOutcome::Verified, reference, detail: None }),
+            Some("n") => Ok(mismatch(reference, body["err"].as_str().map(|err| format!("UIDAI error {}", err)))),
+            _ => Err(HimsError::NetworkError {
+                message: "UIDAI authentication response has no ret".to_string(),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl DemographicVerifier for AbdmVerifier {
+    async fn verify(&self, request: &DemographicVerificationRequest) -> Result<VerificationResult, HimsError> {
+        request.validate()?;
+        match request.document {
+            IdentityDocument::Abha => self.verify_abha(request).await,
+            IdentityDocument::Aadhaar => self.verify_aadhaar(request).await,
+        }
+    }
+}
+
+/// Whether a number's last digit is its Verhoeff check digit
+pub fn verhoeff_valid(digits: &str) -> bool {
+    let mut check = 0usize;
+    for (position, digit) in digits.bytes().rev().enumerate() {
+        if !digit.is_ascii_digit() {
+            return false;
+        }
+        let permuted = VERHOEFF_P[position % 8][(digit - b'0') as usize];
+        check = VERHOEFF_D[check][permuted as usize] as usize;
+    }
+    !digits.is_empty() && check == 0
+}
+
+/// Gender as each authority codes it; ABHA codes other genders `O` and
+/// UIDAI `T`
+fn gender_code(document: IdentityDocument, gender: &Gender) -> Option<&'static str> {
+    match (gender, document) {
+        (Gender::Male, _) => Some("M"),
+        (Gender::Female, _) => Some("F"),
+        (Gender::Other, IdentityDocument::Abha) => Some("O"),
+        (Gender::Other, IdentityDocument::Aadhaar) => Some("T"),
+        (Gender::Unknown, _) => None,
+    }
+}
+
+fn hex_digest(source: &str) -> String {
+    Sha256::digest(source.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn mismatch(reference: Option<String>, detail: Option<String>) -> VerificationResult {
+    VerificationResult { outcome: VerificationOutcome::Mismatch, reference, detail }
+}
+
+/// The message of an ABDM error body, `{"code", "message", "details": [{"message"}]}`
+fn abdm_error_detail(body: &Value) -> Option<String> {
+    body["details"][0]["message"].as_str().or_else(|| body["message"].as_str()).map(str::to_string)
+}
+
+fn network_error(call: &str, e: reqwest::Error) -> HimsError {
+    HimsError::NetworkError { message: format!("{} failed: {}", call, e) }
+}
+
+/// The JSON body of a successful response; server errors are the
+/// authority's, not a verdict on the demographics
+async fn success_body(call: &str, response: reqwest::Response) -> Result<Value, HimsError> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(HimsError::NetworkError { message: format!("{} answered {}: {}", call, status, body) });
+    }
+    let text = response.text().await.map_err(|e| network_error(call, e))?;
+    if text.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_str(&text).map_err(|e| HimsError::NetworkError {
+        message: format!("Invalid {} response: {}", call, e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aadhaar(number: &str) -> DemographicVerificationRequest {
+        DemographicVerificationRequest {
+            document: IdentityDocument::Aadhaar,
+            number: number.to_string(),
+            name: "Ravi  Kumar".to_string(),
+            gender: Gender::Male,
+            date_of_birth: NaiveDate::from_ymd_opt(1990, 4, 12),
+            year_of_birth: None,
+            mobile: None,
+        }
+    }
+
+    #[test]
+    fn aadhaar_numbers_are_checked_and_masked() {
+        let request = aadhaar("4992 1034 5670");
+        assert!(request.validate().is_ok());
+        assert_eq!(request.masked_number().unwrap(), "XXXXXXXX5670");
+        assert!(aadhaar("499210345671").validate().is_err());
+        assert!(aadhaar("099210345670").validate().is_err());
+
+        let mut unknown = aadhaar("499210345670");
+        unknown.gender = Gender::Unknown;
+        assert!(unknown.validate().is_err());
+    }
+
+    #[test]
+    fn cache_keys_ignore_formatting_only() {
+        let request = aadhaar("4992-1034-5670");
+        let mut same = aadhaar("499210345670");
+        same.name = "ravi kumar".to_string();
+        assert_eq!(request.cache_key().unwrap(), same.cache_key().unwrap());
+        assert_eq!(request.number_hash().unwrap(), same.number_hash().unwrap());
+
+        let mut different = aadhaar("499210345670");
+        different.date_of_birth = NaiveDate::from_ymd_opt(1990, 4, 13);
+        assert_ne!(request.cache_key().unwrap(), different.cache_key().unwrap());
+        assert_eq!(request.number_hash().unwrap(), different.number_hash().unwrap());
+        assert!(!request.cache_key().unwrap().contains("499210345670"));
+    }
+}
@@ -29,6 +29,12 @@ impl ConfigurationExample {
         // let mh_config = manager.get_effective_config("MH")?;
         // println!("Maharashtra effective regulations: {:?}", mh_config.base_regulations);
 
+        // Layer a hospital's own rules on top of California
+        manager.register_organization_config(Self::create_hospital_override_config());
+        let hospital_config = manager.get_effective_organization_config("ucsf-health")?;
+        println!("Hospital retention years: {}", hospital_config.base.data_retention_years);
+        println!("Hospital config conflicts: {:?}", hospital_config.conflicts);
+
         // Validate compliance chain
         let ca_compliance = manager.validate_compliance_chain("CA", "patient_access")?;
         println!("California compliance checks: {:?}", ca_compliance);
@@ -74,6 +80,38 @@ impl ConfigurationExample {
         }
     }
 
+    /// Create hospital overrides on top of California. The hospital keeps
+    /// records longer and bans cross-state telemedicine; its attempt to drop
+    /// PDMP reporting is overruled by the stricter state rule.
+    pub fn create_hospital_override_config() -> OrganizationConfig {
+        OrganizationConfig {
+            organization_id: "ucsf-health".to_string(),
+            organization_name: "UCSF Health".to_string(),
+            state_key: "CA".to_string(),
+            additional_regulations: vec![
+                "UC Health Data Governance Policy".to_string(),
+            ],
+            additional_privacy_regulations: vec![],
+            data_retention_years: Some(10),
+            audit_requirements: None,
+            encryption_required: Some(true),
+            telemedicine_rules: Some(TelemedicineRegulations {
+                allowed: true,
+                cross_state_practice: false,
+                prescription_restrictions: vec![
+                    "No Schedule II prescribing via telemedicine".to_string(),
+                ],
+                required_standards: vec![],
+            }),
+            prescription_monitoring: Some(PrescriptionMonitoring {
+                pdmp_required: false,
+                reporting_timeframe_hours: 12,
+                controlled_substances_only: true,
+            }),
+            conflict_resolution: ConflictResolution::Strictest,
+        }
+    }
+
     /// Demonstrate finding states with similar regulations
     pub fn find_similar_states_example(manager: &InheritableConfigManager) -> Vec<String> {
         let ccpa_like_regulations = vec![
@@ -1,20 +1,25 @@
 pub mod usa;
 pub mod india;
-// TODO: Implement remaining country modules
-// pub mod eu;
-// pub mod canada;
-// pub mod australia;
-// pub mod uk;
+pub mod eu;
+pub mod canada;
+pub mod australia;
+pub mod uk;
 pub mod common;
+pub mod compliance;
+pub mod residency;
 pub mod inheritance_examples;
 
+// Each country's `states` module is reachable by its path only
+#[allow(ambiguous_glob_reexports)]
 pub use usa::*;
 pub use india::*;
-// pub use eu::*;
-// pub use canada::*;
-// pub use australia::*;
-// pub use uk::*;
+pub use eu::*;
+pub use canada::*;
+pub use australia::*;
+pub use uk::*;
 pub use common::*;
+pub use compliance::*;
+pub use residency::*;
 pub use inheritance_examples::*;
 
 use serde::{Deserialize, Serialize};
@@ -34,10 +39,23 @@ pub struct CountryConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegulatoryFramework {
     pub primary_authority: String,
-    pub compliance_standards: Vec<String>,
+    pub requirements: Vec<ComplianceRequirement>,
     pub audit_requirements: AuditRequirements,
 }
 
+impl RegulatoryFramework {
+    /// Names of the regulations and standards the requirements come from
+    pub fn standards(&self) -> Vec<String> {
+        let mut standards: Vec<String> = Vec::new();
+        for requirement in &self.requirements {
+            if !standards.contains(&requirement.standard) {
+                standards.push(requirement.standard.clone());
+            }
+        }
+        standards
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditRequirements {
     pub retention_period_years: u32,
@@ -63,11 +81,13 @@ impl CountryRegistry {
         // Initialize with default country configurations
         self.register_country(usa::get_usa_config());
         self.register_country(india::get_india_config());
-        // TODO: Register remaining countries as they are implemented
-        // self.register_country(eu::get_eu_config());
-        // self.register_country(canada::get_canada_config());
-        // self.register_country(australia::get_australia_config());
-        // self.register_country(uk::get_uk_config());
+        self.register_country(eu::get_eu_config());
+        for member_state in eu::EuMemberStateRegistry::new().list_member_states() {
+            self.register_country(member_state.to_country_config());
+        }
+        self.register_country(canada::get_canada_config());
+        self.register_country(australia::get_australia_config());
+        self.register_country(uk::get_uk_config());
     }
 
     pub fn register_country(&mut self, config: CountryConfig) {
@@ -89,4 +109,66 @@ impl Default for CountryRegistry {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_countries() {
+        let registry = CountryRegistry::new();
+        let mut codes: Vec<&str> = registry.list_supported_countries().into_iter().map(String::as_str).collect();
+        codes.sort();
+        assert_eq!(codes, vec!["AU", "CA", "DE", "EU", "FR", "GB", "IN", "NL", "US"]);
+        assert!(matches!(
+            registry.get_country_config("ZZ"),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_uk_config() {
+        let registry = CountryRegistry::new();
+        let uk = registry.get_country_config("GB").unwrap();
+        let framework = &uk.regulatory_framework;
+        assert_eq!(framework.primary_authority, "Information Commissioner's Office");
+        assert_eq!(framework.audit_requirements.retention_period_years, 8);
+        assert!(framework.audit_requirements.third_party_audit_required);
+        assert!(!uk.data_localization_required);
+
+        let standards = framework.standards();
+        assert_eq!(standards.len(), 6);
+        assert!(standards.contains(&"NHS Data Security and Protection Toolkit (DSPT)".to_string()));
+        let ids: Vec<&str> = framework.requirements.iter().map(|r| r.id.as_str()).collect();
+        assert!(ids.contains(&"UK-GDPR-ART33"));
+        assert!(ids.contains(&"DSPT-4"));
+    }
+
+    #[test]
+    fn test_canada_config() {
+        let registry = CountryRegistry::new();
+        let canada = registry.get_country_config("CA").unwrap();
+        assert_eq!(canada.regulatory_framework.audit_requirements.retention_period_years, 10);
+        assert_eq!(canada.regulatory_framework.standards()[0], "PIPEDA");
+        assert!(canada.privacy_regulations.contains(&"PIPEDA".to_string()));
+        assert!(canada.supported_standards.contains(&"ICD-10-CA".to_string()));
+    }
+
+    #[test]
+    fn test_australia_config() {
+        let registry = CountryRegistry::new();
+        let australia = registry.get_country_config("AU").unwrap();
+        assert!(australia.data_localization_required);
+        assert_eq!(australia.regulatory_framework.audit_requirements.retention_period_years, 7);
+        let residency = australia
+            .regulatory_framework
+            .requirements
+            .iter()
+            .find(|r| r.id == "MHRA-S77")
+            .unwrap();
+        assert_eq!(
+            residency.predicate,
+            RequirementPredicate::DataResidentIn { country_code: "AU".to_string() }
+        );
+    }
+}
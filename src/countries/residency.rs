@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::HimsError;
+use crate::countries::CountryConfig;
+use crate::models::{AuditLog, AuditOutcome};
+use crate::modules::audit::AuditService;
+
+/// Where data is physically stored or sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataRegion {
+    /// ISO 3166-1 alpha-2 country code
+    pub country_code: String,
+    /// Provider region, e.g. `ap-south-1`
+    pub region: Option<String>,
+}
+
+impl DataRegion {
+    pub fn new(country_code: &str, region: Option<&str>) -> Self {
+        Self {
+            country_code: country_code.to_ascii_uppercase(),
+            region: region.map(str::to_string),
+        }
+    }
+
+    /// Region configured for this deployment through `HIMS_DATA_COUNTRY`
+    /// and `HIMS_DATA_REGION`
+    pub fn from_env() -> Option<Self> {
+        let country = std::env::var("HIMS_DATA_COUNTRY").ok()?;
+        let region = std::env::var("HIMS_DATA_REGION").ok();
+        Some(Self::new(&country, region.as_deref()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageKind {
+    PrimaryDatabase,
+    Replica,
+    Backup,
+    ObjectStore,
+    Cache,
+    LogArchive,
+}
+
+/// A place health data is stored, tagged with its region
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageTarget {
+    pub name: String,
+    pub kind: StorageKind,
+    pub region: DataRegion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DestinationKind {
+    FileExport,
+    ApiAdapter,
+    Messaging,
+}
+
+/// A system health data is exported or synchronised to, tagged with its region
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportDestination {
+    pub name: String,
+    pub kind: DestinationKind,
+    pub region: DataRegion,
+}
+
+/// What happens when data would leave the permitted countries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResidencyEnforcement {
+    Block,
+    Warn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResidencyViolation {
+    pub country_code: String,
+    pub target: String,
+    pub target_country: String,
+    pub enforcement: ResidencyEnforcement,
+    pub message: String,
+}
+
+impl ResidencyViolation {
+    /// Compliance audit event for the violation
+    pub fn audit_log(&self) -> AuditLog {
+        let outcome = match self.enforcement {
+            ResidencyEnforcement::Block => AuditOutcome::SeriousFailure,
+            ResidencyEnforcement::Warn => AuditOutcome::MinorFailure,
+        };
+        AuditLog::compliance_violation(outcome, format!("data-residency: {}", self.message))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ResidencyDecision {
+    Allowed,
+    Warned(ResidencyViolation),
+    Blocked(ResidencyViolation),
+}
+
+impl ResidencyDecision {
+    pub fn violation(&self) -> Option<&ResidencyViolation> {
+        match self {
+            ResidencyDecision::Allowed => None,
+            ResidencyDecision::Warned(violation) | ResidencyDecision::Blocked(violation) => Some(violation),
+        }
+    }
+
+    /// Err for blocked operations; warnings are allowed through
+    pub fn into_result(self) -> Result<(), HimsError> {
+        match self {
+            ResidencyDecision::Blocked(violation) => Err(HimsError::SecurityError {
+                message: violation.message,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Data residency rules derived from a country configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataResidencyPolicy {
+    pub country_code: String,
+    /// False when the country does not require localization
+    pub localization_required: bool,
+    pub enforcement: ResidencyEnforcement,
+    /// Countries besides the home country data may be held in
+    pub additional_countries: Vec<String>,
+}
+
+impl DataResidencyPolicy {
+    pub fn for_country(country: &CountryConfig, enforcement: ResidencyEnforcement) -> Self {
+        Self {
+            country_code: country.country_code.clone(),
+            localization_required: country.data_localization_required,
+            enforcement,
+            additional_countries: Vec::new(),
+        }
+    }
+
+    /// Permit another country, e.g. for a disaster-recovery site approved
+    /// by the regulator
+    pub fn allow_country(mut self, country_code: &str) -> Self {
+        self.additional_countries.push(country_code.to_ascii_uppercase());
+        self
+    }
+
+    pub fn permits(&self, region: &DataRegion) -> bool {
+        !self.localization_required
+            || region.country_code == self.country_code
+            || self.additional_countries.contains(&region.country_code)
+    }
+
+    pub fn check_storage(&self, target: &StorageTarget) -> ResidencyDecision {
+        self.decide(&target.name, &target.region, "Storage target")
+    }
+
+    pub fn check_export(&self, destination: &ExportDestination) -> ResidencyDecision {
+        self.decide(&destination.name, &destination.region, "Export destination")
+    }
+
+    /// Violations across all configured storage targets, for startup checks
+    pub fn validate_storage_targets(&self, targets: &[StorageTarget]) -> Vec<ResidencyViolation> {
+        targets
+            .iter()
+            .filter_map(|target| self.check_storage(target).violation().cloned())
+            .collect()
+    }
+
+    /// Check an export, record any violation as a compliance audit event
+    /// and fail if the policy blocks it
+    pub async fn enforce_export(&self, destination: &ExportDestination, audit_service: &AuditService) -> Result<(), HimsError> {
+        let decision = self.check_export(destination);
+        if let Some(violation) = decision.violation() {
+            tracing::warn!("Data residency violation: {}", violation.message);
+            if let Err(e) = audit_service.create_audit_log(&violation.audit_log()).await {
+                tracing::error!("Failed to write data residency audit event: {}", e);
+            }
+        }
+        decision.into_result()
+    }
+
+    fn decide(&self, target: &str, region: &DataRegion, label: &str) -> ResidencyDecision {
+        if self.permits(region) {
+            return ResidencyDecision::Allowed;
+        }
+
+        let violation = ResidencyViolation {
+            country_code: self.country_code.clone(),
+            target: target.to_string(),
+            target_country: region.country_code.clone(),
+            enforcement: self.enforcement,
+            message: format!(
+                "{} '{}' is in {} but {} requires data to stay in-country",
+                label, target, region.country_code, self.country_code
+            ),
+        };
+        match self.enforcement {
+            ResidencyEnforcement::Block => ResidencyDecision::Blocked(violation),
+            ResidencyEnforcement::Warn => ResidencyDecision::Warned(violation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::countries::CountryRegistry;
+    use crate::models::AuditEventType;
+
+    fn policy(country_code: &str, enforcement: ResidencyEnforcement) -> DataResidencyPolicy {
+        let registry = CountryRegistry::new();
+        DataResidencyPolicy::for_country(registry.get_country_config(country_code).unwrap(), enforcement)
+    }
+
+    fn storage(name: &str, country_code: &str) -> StorageTarget {
+        StorageTarget {
+            name: name.to_string(),
+            kind: StorageKind::Backup,
+            region: DataRegion::new(country_code, None),
+        }
+    }
+
+    fn destination(country_code: &str) -> ExportDestination {
+        ExportDestination {
+            name: "registry-feed".to_string(),
+            kind: DestinationKind::ApiAdapter,
+            region: DataRegion::new(country_code, Some("us-east-1")),
+        }
+    }
+
+    #[test]
+    fn test_permits() {
+        let australia = policy("AU", ResidencyEnforcement::Block);
+        assert!(australia.localization_required);
+        assert!(australia.permits(&DataRegion::new("au", Some("ap-southeast-2"))));
+        assert!(!australia.permits(&DataRegion::new("NZ", None)));
+        assert!(australia.clone().allow_country("nz").permits(&DataRegion::new("NZ", None)));
+
+        // Countries without localization rules permit any region
+        let uk = policy("GB", ResidencyEnforcement::Block);
+        assert!(!uk.localization_required);
+        assert!(uk.permits(&DataRegion::new("US", None)));
+    }
+
+    #[test]
+    fn test_blocked_export() {
+        let decision = policy("AU", ResidencyEnforcement::Block).check_export(&destination("US"));
+        let violation = decision.violation().unwrap().clone();
+        assert_eq!(violation.target, "registry-feed");
+        assert_eq!(violation.target_country, "US");
+        assert_eq!(
+            violation.message,
+            "Export destination 'registry-feed' is in US but AU requires data to stay in-country"
+        );
+        assert!(matches!(decision.into_result(), Err(HimsError::SecurityError { .. })));
+
+        let audit = violation.audit_log();
+        assert!(matches!(audit.event_type, AuditEventType::ComplianceViolation));
+        assert_eq!(audit.outcome, "serious-failure");
+        assert_eq!(audit.details.unwrap(), format!("data-residency: {}", violation.message));
+    }
+
+    #[test]
+    fn test_warned_export() {
+        let decision = policy("AU", ResidencyEnforcement::Warn).check_export(&destination("US"));
+        assert!(matches!(decision, ResidencyDecision::Warned(_)));
+        assert_eq!(decision.violation().unwrap().audit_log().outcome, "minor-failure");
+        assert!(decision.into_result().is_ok());
+
+        let allowed = policy("AU", ResidencyEnforcement::Block).check_export(&destination("AU"));
+        assert!(matches!(allowed, ResidencyDecision::Allowed));
+        assert!(allowed.violation().is_none());
+    }
+
+    #[test]
+    fn test_validate_storage_targets() {
+        let targets = vec![
+            storage("primary-database", "AU"),
+            storage("offsite-backup", "SG"),
+            storage("dr-replica", "NZ"),
+        ];
+        let policy = policy("AU", ResidencyEnforcement::Block);
+        let violations = policy.validate_storage_targets(&targets);
+        let names: Vec<&str> = violations.iter().map(|v| v.target.as_str()).collect();
+        assert_eq!(names, vec!["offsite-backup", "dr-replica"]);
+        assert!(violations[0].message.starts_with("Storage target 'offsite-backup'"));
+
+        let violations = policy.allow_country("NZ").validate_storage_targets(&targets);
+        assert_eq!(violations.len(), 1);
+    }
+}
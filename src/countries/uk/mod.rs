@@ -0,0 +1,42 @@
+use crate::countries::{CountryConfig, RegulatoryFramework, AuditRequirements};
+use crate::countries::compliance::requirements_for_standards;
+
+/// Get United Kingdom configuration
+pub fn get_uk_config() -> CountryConfig {
+    CountryConfig {
+        country_code: "GB".to_string(),
+        country_name: "United Kingdom".to_string(),
+        regulatory_framework: RegulatoryFramework {
+            primary_authority: "Information Commissioner's Office".to_string(),
+            requirements: requirements_for_standards(&[
+                "UK GDPR",
+                "Data Protection Act 2018",
+                "NHS Data Security and Protection Toolkit (DSPT)",
+                "DCB0129 Clinical Risk Management",
+                "DCB0160 Clinical Risk Management",
+                "Common Law Duty of Confidentiality",
+            ]),
+            audit_requirements: AuditRequirements {
+                // Records Management Code of Practice: adult records 8 years after last treatment
+                retention_period_years: 8,
+                real_time_monitoring: true,
+                third_party_audit_required: true,
+            },
+        },
+        data_localization_required: false,
+        supported_standards: vec![
+            "FHIR R4".to_string(),
+            "UK Core FHIR profiles".to_string(),
+            "HL7v2".to_string(),
+            "SNOMED CT".to_string(),
+            "dm+d".to_string(),
+            "ICD-10".to_string(),
+            "OPCS-4".to_string(),
+        ],
+        privacy_regulations: vec![
+            "UK GDPR".to_string(),
+            "Data Protection Act 2018".to_string(),
+            "National Data Opt-out".to_string(),
+        ],
+    }
+}
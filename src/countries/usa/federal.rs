@@ -54,7 +54,7 @@ impl UsFederalCompliance {
         }
     }
 
-    pub fn validate_hitech_compliance(operation: &str) -> Result<bool, crate::core::HimsError> {
+    pub fn validate_hitech_compliance(_operation: &str) -> Result<bool, crate::core::HimsError> {
         // Implement HITECH validation logic
         Ok(true) // Placeholder
     }
@@ -2,6 +2,7 @@ pub mod states;
 pub mod federal;
 
 use crate::countries::{CountryConfig, RegulatoryFramework, AuditRequirements};
+use crate::countries::compliance::requirements_for_standards;
 
 /// Get USA configuration
 pub fn get_usa_config() -> CountryConfig {
@@ -10,12 +11,12 @@ pub fn get_usa_config() -> CountryConfig {
         country_name: "United States".to_string(),
         regulatory_framework: RegulatoryFramework {
             primary_authority: "HHS - Department of Health and Human Services".to_string(),
-            compliance_standards: vec![
-                "HIPAA".to_string(),
-                "HITECH".to_string(),
-                "21 CFR Part 11".to_string(),
-                "SOX".to_string(),
-            ],
+            requirements: requirements_for_standards(&[
+                "HIPAA",
+                "HITECH",
+                "21 CFR Part 11",
+                "SOX",
+            ]),
             audit_requirements: AuditRequirements {
                 retention_period_years: 6,
                 real_time_monitoring: true,
@@ -63,13 +63,13 @@ pub struct CaliforniaHealthcare;
 
 impl CaliforniaHealthcare {
     /// Validate CCPA compliance for healthcare data
-    pub fn validate_ccpa_compliance(data_processing: &str) -> Result<bool, crate::core::HimsError> {
+    pub fn validate_ccpa_compliance(_data_processing: &str) -> Result<bool, crate::core::HimsError> {
         // Implement CCPA compliance validation
         Ok(true) // Placeholder
     }
 
     /// Check medical board license validity
-    pub fn verify_medical_license(license_number: &str) -> Result<bool, crate::core::HimsError> {
+    pub fn verify_medical_license(_license_number: &str) -> Result<bool, crate::core::HimsError> {
         // Integrate with California Medical Board API
         Ok(true) // Placeholder
     }
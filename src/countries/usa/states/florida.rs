@@ -1,66 +0,0 @@
-// Placeholder implementations for remaining US states
-
-use crate::countries::usa::states::{StateConfig, TelemedicineRegulations, PrescriptionMonitoring};
-
-pub fn get_florida_config() -> StateConfig {
-    StateConfig {
-        state_code: "FL".to_string(),
-        state_name: "Florida".to_string(),
-        additional_regulations: vec!["Florida Personal Information Protection Act".to_string()],
-        state_licensing_requirements: vec!["Florida Department of Health License".to_string()],
-        data_breach_notification_laws: vec!["Florida Statute 817.5681".to_string()],
-        telemedicine_regulations: TelemedicineRegulations {
-            allowed: true,
-            cross_state_practice: false,
-            prescription_restrictions: vec!["Standard DEA requirements".to_string()],
-            required_standards: vec!["HIPAA compliance".to_string()],
-        },
-        prescription_monitoring: PrescriptionMonitoring {
-            pdmp_required: true,
-            reporting_timeframe_hours: 24,
-            controlled_substances_only: true,
-        },
-    }
-}
-
-pub fn get_new_york_config() -> StateConfig {
-    StateConfig {
-        state_code: "NY".to_string(),
-        state_name: "New York".to_string(),
-        additional_regulations: vec!["NY SHIELD Act".to_string()],
-        state_licensing_requirements: vec!["New York State Education Department License".to_string()],
-        data_breach_notification_laws: vec!["NY General Business Law Section 899-aa".to_string()],
-        telemedicine_regulations: TelemedicineRegulations {
-            allowed: true,
-            cross_state_practice: false,
-            prescription_restrictions: vec!["NYS specific requirements".to_string()],
-            required_standards: vec!["NYS approved platforms".to_string()],
-        },
-        prescription_monitoring: PrescriptionMonitoring {
-            pdmp_required: true,
-            reporting_timeframe_hours: 24,
-            controlled_substances_only: true,
-        },
-    }
-}
-
-pub fn get_illinois_config() -> StateConfig {
-    StateConfig {
-        state_code: "IL".to_string(),
-        state_name: "Illinois".to_string(),
-        additional_regulations: vec!["Illinois Personal Information Protection Act".to_string()],
-        state_licensing_requirements: vec!["Illinois Department of Financial and Professional Regulation License".to_string()],
-        data_breach_notification_laws: vec!["815 ILCS 530".to_string()],
-        telemedicine_regulations: TelemedicineRegulations {
-            allowed: true,
-            cross_state_practice: false,
-            prescription_restrictions: vec!["Standard requirements".to_string()],
-            required_standards: vec!["HIPAA compliance".to_string()],
-        },
-        prescription_monitoring: PrescriptionMonitoring {
-            pdmp_required: true,
-            reporting_timeframe_hours: 24,
-            controlled_substances_only: true,
-        },
-    }
-}
\ No newline at end of file
@@ -1 +0,0 @@
-pub use crate::countries::usa::states::florida::*;
\ No newline at end of file
@@ -1,43 +1,42 @@
 pub mod california;
-pub mod texas;
-pub mod florida;
-pub mod new_york;
-pub mod illinois;
-// TODO: Implement remaining state modules
-// pub mod pennsylvania;
-// pub mod ohio;
-// pub mod georgia;
-// pub mod north_carolina;
-// pub mod michigan;
+// Rules for every state, DC and the territories are data-driven; see data/us_states.json
 
 use serde::{Deserialize, Serialize};
 use crate::core::HimsError;
+pub use crate::countries::common::{PrescriptionMonitoring, TelemedicineRegulations};
+
+/// Embedded definitions for all 50 states, DC and the territories
+const US_STATES_JSON: &str = include_str!("data/us_states.json");
 
 /// State-specific healthcare configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StateConfig {
     pub state_code: String,
     pub state_name: String,
+    #[serde(default)]
+    pub jurisdiction: UsJurisdiction,
     pub additional_regulations: Vec<String>,
     pub state_licensing_requirements: Vec<String>,
     pub data_breach_notification_laws: Vec<String>,
+    /// Statutory deadline for notifying affected residents; None where the
+    /// law only requires notice without unreasonable delay
+    #[serde(default)]
+    pub breach_notification_days: Option<u32>,
     pub telemedicine_regulations: TelemedicineRegulations,
     pub prescription_monitoring: PrescriptionMonitoring,
+    /// Name of the state prescription drug monitoring program
+    #[serde(default)]
+    pub pdmp_program: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TelemedicineRegulations {
-    pub allowed: bool,
-    pub cross_state_practice: bool,
-    pub prescription_restrictions: Vec<String>,
-    pub required_standards: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PrescriptionMonitoring {
-    pub pdmp_required: bool,
-    pub reporting_timeframe_hours: u32,
-    pub controlled_substances_only: bool,
+/// Kind of US jurisdiction a configuration applies to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UsJurisdiction {
+    #[default]
+    State,
+    District,
+    Territory,
 }
 
 /// State registry for US healthcare regulations
@@ -55,33 +54,22 @@ impl UsStateRegistry {
     }
 
     fn initialize_states(&mut self) {
-        // Convert InheritableStateConfig to StateConfig for California
-        let ca_inheritable = california::get_california_config();
-        let ca_config = StateConfig {
-            state_code: ca_inheritable.state_code,
-            state_name: ca_inheritable.state_name,
-            additional_regulations: ca_inheritable.additional_regulations,
-            state_licensing_requirements: vec!["California Medical Board License".to_string()],
-            data_breach_notification_laws: vec!["California Civil Code Section 1798.82".to_string()],
-            telemedicine_regulations: TelemedicineRegulations {
-                allowed: true,
-                cross_state_practice: false,
-                prescription_restrictions: vec!["Controlled substances require in-person visit".to_string()],
-                required_standards: vec!["CMBC Telemedicine Guidelines".to_string()],
-            },
-            prescription_monitoring: PrescriptionMonitoring {
-                pdmp_required: true,
-                reporting_timeframe_hours: 24,
-                controlled_substances_only: true,
-            },
-        };
-        self.register_state(ca_config);
-        
-        self.register_state(texas::get_texas_config());
-        self.register_state(florida::get_florida_config());
-        self.register_state(new_york::get_new_york_config());
-        self.register_state(illinois::get_illinois_config());
-        // Add more states as needed
+        let states = load_states_from_json(US_STATES_JSON)
+            .expect("embedded US state definitions are valid");
+        for config in states {
+            self.register_state(config);
+        }
+    }
+
+    /// Register additional or replacement definitions, e.g. from a
+    /// deployment-specific JSON file
+    pub fn load_from_json(&mut self, json: &str) -> Result<usize, HimsError> {
+        let states = load_states_from_json(json)?;
+        let count = states.len();
+        for config in states {
+            self.register_state(config);
+        }
+        Ok(count)
     }
 
     pub fn register_state(&mut self, config: StateConfig) {
@@ -94,13 +82,45 @@ impl UsStateRegistry {
         })
     }
 
-    pub fn validate_compliance(&self, state_code: &str, operation: &str) -> Result<bool, HimsError> {
-        let config = self.get_state_config(state_code)?;
+    /// All registered states, sorted by code
+    pub fn list_states(&self) -> Vec<&StateConfig> {
+        let mut states: Vec<&StateConfig> = self.states.values().collect();
+        states.sort_by(|a, b| a.state_code.cmp(&b.state_code));
+        states
+    }
+
+    /// Registered states of one jurisdiction kind, sorted by code
+    pub fn list_by_jurisdiction(&self, jurisdiction: UsJurisdiction) -> Vec<&StateConfig> {
+        self.list_states()
+            .into_iter()
+            .filter(|state| state.jurisdiction == jurisdiction)
+            .collect()
+    }
+
+    pub fn validate_compliance(&self, state_code: &str, _operation: &str) -> Result<bool, HimsError> {
+        let _config = self.get_state_config(state_code)?;
         // Implement state-specific compliance validation
         Ok(true) // Placeholder
     }
 }
 
+/// Parse state definitions in the `StateConfig` JSON shape
+pub fn load_states_from_json(json: &str) -> Result<Vec<StateConfig>, HimsError> {
+    let states: Vec<StateConfig> = serde_json::from_str(json).map_err(|e| HimsError::ConfigurationError {
+        message: format!("Invalid US state definitions: {}", e),
+    })?;
+
+    for state in &states {
+        let valid_code = state.state_code.len() == 2 && state.state_code.chars().all(|c| c.is_ascii_uppercase());
+        if !valid_code {
+            return Err(HimsError::ConfigurationError {
+                message: format!("Invalid US state code: {}", state.state_code),
+            });
+        }
+    }
+    Ok(states)
+}
+
 impl Default for UsStateRegistry {
     fn default() -> Self {
         Self::new()
@@ -108,7 +128,86 @@ impl Default for UsStateRegistry {
 }
 
 pub use california::*;
-pub use texas::*;
-pub use florida::*;
-pub use new_york::*;
-pub use illinois::*;
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTRA_STATE: &str = r#"[{
+        "state_code": "ZZ",
+        "state_name": "Test State",
+        "additional_regulations": [],
+        "state_licensing_requirements": [],
+        "data_breach_notification_laws": [],
+        "telemedicine_regulations": {
+            "allowed": true,
+            "cross_state_practice": false,
+            "prescription_restrictions": [],
+            "required_standards": []
+        },
+        "prescription_monitoring": {
+            "pdmp_required": false,
+            "reporting_timeframe_hours": 0,
+            "controlled_substances_only": true
+        }
+    }]"#;
+
+    #[test]
+    fn test_load_states_from_json() {
+        let states = load_states_from_json(US_STATES_JSON).unwrap();
+        assert_eq!(states.len(), 56);
+
+        // Optional fields default; a state is assumed without a jurisdiction
+        let extra = load_states_from_json(EXTRA_STATE).unwrap();
+        assert_eq!(extra[0].jurisdiction, UsJurisdiction::State);
+        assert_eq!(extra[0].breach_notification_days, None);
+        assert_eq!(extra[0].pdmp_program, None);
+
+        for code in ["zz", "ZZZ", "Z1"] {
+            let invalid = EXTRA_STATE.replace("\"ZZ\"", &format!("\"{}\"", code));
+            assert!(matches!(
+                load_states_from_json(&invalid),
+                Err(HimsError::ConfigurationError { .. })
+            ));
+        }
+        assert!(load_states_from_json("{\"state_code\": \"ZZ\"}").is_err());
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = UsStateRegistry::new();
+        let texas = registry.get_state_config("TX").unwrap();
+        assert_eq!(texas.state_name, "Texas");
+        assert_eq!(texas.prescription_monitoring.reporting_timeframe_hours, 72);
+        assert_eq!(texas.breach_notification_days, Some(60));
+        assert!(!registry.get_state_config("VI").unwrap().prescription_monitoring.pdmp_required);
+        assert!(registry.get_state_config("tx").is_err());
+
+        // Deployment definitions add to or replace the embedded ones
+        assert_eq!(registry.load_from_json(EXTRA_STATE).unwrap(), 1);
+        assert_eq!(registry.list_states().len(), 57);
+        assert_eq!(registry.get_state_config("ZZ").unwrap().state_name, "Test State");
+        let replaced = EXTRA_STATE.replace("\"ZZ\"", "\"TX\"");
+        registry.load_from_json(&replaced).unwrap();
+        assert_eq!(registry.get_state_config("TX").unwrap().state_name, "Test State");
+        assert_eq!(registry.list_states().len(), 57);
+    }
+
+    #[test]
+    fn test_list_by_jurisdiction() {
+        let registry = UsStateRegistry::new();
+        let codes = |jurisdiction| -> Vec<String> {
+            registry
+                .list_by_jurisdiction(jurisdiction)
+                .iter()
+                .map(|state| state.state_code.clone())
+                .collect()
+        };
+
+        let states = codes(UsJurisdiction::State);
+        assert_eq!(states.len(), 50);
+        assert_eq!(states.first().map(String::as_str), Some("AK"));
+        assert!(states.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(codes(UsJurisdiction::District), vec!["DC"]);
+        assert_eq!(codes(UsJurisdiction::Territory), vec!["AS", "GU", "MP", "PR", "VI"]);
+    }
+}
@@ -1,2 +0,0 @@
-// Implement the other state configurations
-pub use crate::countries::usa::states::florida::*;
\ No newline at end of file
@@ -1,51 +0,0 @@
-use crate::countries::usa::states::{StateConfig, TelemedicineRegulations, PrescriptionMonitoring};
-
-/// Texas healthcare configuration
-pub fn get_texas_config() -> StateConfig {
-    StateConfig {
-        state_code: "TX".to_string(),
-        state_name: "Texas".to_string(),
-        additional_regulations: vec![
-            "Texas Identity Theft Enforcement and Protection Act".to_string(),
-            "Texas Medical Privacy Act".to_string(),
-            "House Bill 300 (Data Breach Notification)".to_string(),
-        ],
-        state_licensing_requirements: vec![
-            "Texas Medical Board License".to_string(),
-            "Texas Board of Nursing License".to_string(),
-            "Texas State Board of Pharmacy License".to_string(),
-        ],
-        data_breach_notification_laws: vec![
-            "Texas Business and Commerce Code Chapter 521".to_string(),
-        ],
-        telemedicine_regulations: TelemedicineRegulations {
-            allowed: true,
-            cross_state_practice: true, // With proper licensing
-            prescription_restrictions: vec![
-                "DEA registration required for controlled substances".to_string(),
-                "Patient-physician relationship must be established".to_string(),
-            ],
-            required_standards: vec![
-                "HIPAA compliant platform".to_string(),
-                "Texas Medical Board approved technology".to_string(),
-            ],
-        },
-        prescription_monitoring: PrescriptionMonitoring {
-            pdmp_required: true,
-            reporting_timeframe_hours: 72,
-            controlled_substances_only: true,
-        },
-    }
-}
-
-pub struct TexasHealthcare;
-
-impl TexasHealthcare {
-    pub fn validate_texas_medical_privacy_act(data: &str) -> Result<bool, crate::core::HimsError> {
-        Ok(true) // Placeholder
-    }
-
-    pub fn verify_texas_medical_license(license_number: &str) -> Result<bool, crate::core::HimsError> {
-        Ok(true) // Placeholder
-    }
-}
\ No newline at end of file
@@ -2,6 +2,11 @@ use sqlx::{PgPool, Row};
 use std::time::Duration;
 use anyhow::{Context, Result};
 
+use crate::countries::residency::{DataRegion, StorageKind, StorageTarget};
+use crate::database::migrations::run_migrations;
+use crate::database::provenance::apply_change_context;
+use crate::database::tenant::apply_tenant;
+
 /// Database configuration for healthcare systems
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
@@ -11,6 +16,8 @@ pub struct DatabaseConfig {
     pub connect_timeout: Duration,
     pub idle_timeout: Duration,
     pub max_lifetime: Duration,
+    /// Where the database is hosted, for data residency checks
+    pub data_region: Option<DataRegion>,
 }
 
 impl Default for DatabaseConfig {
@@ -23,10 +30,22 @@ impl Default for DatabaseConfig {
             connect_timeout: Duration::from_secs(10),
             idle_timeout: Duration::from_secs(600), // 10 minutes
             max_lifetime: Duration::from_secs(3600), // 1 hour
+            data_region: DataRegion::from_env(),
         }
     }
 }
 
+impl DatabaseConfig {
+    /// The database as a tagged storage target, if its region is known
+    pub fn storage_target(&self) -> Option<StorageTarget> {
+        self.data_region.clone().map(|region| StorageTarget {
+            name: "primary-database".to_string(),
+            kind: StorageKind::PrimaryDatabase,
+            region,
+        })
+    }
+}
+
 /// Database connection pool for healthcare applications
 #[derive(Debug, Clone)]
 pub struct Database {
@@ -45,6 +64,16 @@ impl Database {
             .idle_timeout(config.idle_timeout)
             .max_lifetime(config.max_lifetime)
             .test_before_acquire(true) // Ensure connections are healthy
+            // Scope every connection to the acquiring task's tenant (row-level security)
+            // and attribute resource history to its user
+            .after_connect(|conn, _meta| Box::pin(async move {
+                apply_tenant(conn).await?;
+                apply_change_context(conn).await
+            }))
+            .before_acquire(|conn, _meta| Box::pin(async move {
+                apply_tenant(conn).await?;
+                apply_change_context(conn).await.map(|_| true)
+            }))
             .connect(&config.database_url)
             .await
             .context("Failed to create database connection pool")?;
@@ -68,15 +97,7 @@ impl Database {
 
     /// Run database migrations for healthcare schema
     pub async fn migrate(&self) -> Result<()> {
-        tracing::info!("Running database migrations for healthcare schema");
-        
-        sqlx::migrate!("./migrations")
-            .run(&self.pool)
-            .await
-            .context("Failed to run database migrations")?;
-            
-        tracing::info!("Database migrations completed successfully");
-        Ok(())
+        run_migrations(&self.pool).await
     }
 
     /// Health check for database connection
@@ -157,7 +178,7 @@ impl<'a> DatabaseTransaction<'a> {
     }
 
     /// Get mutable reference to the transaction
-    pub fn as_mut(&mut self) -> &mut sqlx::Transaction<'a, sqlx::Postgres> {
+    pub fn transaction_mut(&mut self) -> &mut sqlx::Transaction<'a, sqlx::Postgres> {
         &mut self.tx
     }
 }
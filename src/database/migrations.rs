@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+/// Schema migrations embedded from `migrations/` at build time: patients,
+/// appointments, medical records, audit and the authorization_* tables
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// One embedded migration and whether the database has it
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+/// Apply all pending migrations. Safe to run on every start; applied
+/// migrations are skipped, and one whose file has changed since it was
+/// applied is an error.
+pub async fn run_migrations(pool: &PgPool) -> Result<()> {
+    tracing::info!("Running database migrations for healthcare schema");
+
+    MIGRATOR
+        .run(pool)
+        .await
+        .context("Failed to run database migrations")?;
+
+    tracing::info!("Database migrations completed successfully");
+    Ok(())
+}
+
+/// Applied and pending state of every embedded migration, oldest first
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>> {
+    let mut conn = pool.acquire().await.context("Failed to acquire database connection")?;
+    conn.ensure_migrations_table()
+        .await
+        .context("Failed to create migrations table")?;
+    let applied: HashSet<i64> = conn
+        .list_applied_migrations()
+        .await
+        .context("Failed to list applied migrations")?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+
+    Ok(MIGRATOR
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            applied: applied.contains(&migration.version),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        let versions: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_authorization_tables_precede_changelog() {
+        let position = |description: &str| MIGRATOR.iter().position(|m| m.description == description);
+        let tables = position("authorization tables").expect("authorization tables migration");
+        let changelog = position("authorization changelog").expect("authorization changelog migration");
+        let schema = position("initial healthcare schema").expect("initial schema migration");
+        assert!(schema < tables && tables < changelog);
+    }
+}
@@ -1,3 +1,9 @@
 pub mod connection;
+pub mod migrations;
+pub mod provenance;
+pub mod tenant;
 
-pub use connection::{Database, DatabaseConfig, DatabaseStats, DatabaseTransaction};
\ No newline at end of file
+pub use connection::{Database, DatabaseConfig, DatabaseStats, DatabaseTransaction};
+pub use migrations::{migration_status, run_migrations, MigrationStatus, MIGRATOR};
+pub use provenance::{current_change_context, with_change_context, ChangeContext};
+pub use tenant::{current_tenant, current_tenant_id, with_tenant, TenantContext, DEFAULT_TENANT_ID};
\ No newline at end of file
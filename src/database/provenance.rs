@@ -0,0 +1,78 @@
+use sqlx::PgConnection;
+use std::future::Future;
+use uuid::Uuid;
+
+tokio::task_local! {
+    static CURRENT_CHANGE: ChangeContext;
+}
+
+/// Who is making changes in a unit of work, why, and through which system.
+/// Applied to pooled connections as Postgres session settings that the
+/// resource history trigger records with each new version.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeContext {
+    pub user_id: Option<Uuid>,
+    pub reason: Option<String>,
+    /// System the change came from, such as a device or an interface;
+    /// `None` for this server's own API
+    pub source: Option<String>,
+}
+
+impl ChangeContext {
+    pub fn new(user_id: Option<Uuid>, reason: Option<String>) -> Self {
+        Self {
+            user_id,
+            reason,
+            source: None,
+        }
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+/// Run a future with a change context. Like tenants, tasks spawned from
+/// inside do not inherit it.
+pub async fn with_change_context<F: Future>(context: ChangeContext, future: F) -> F::Output {
+    CURRENT_CHANGE.scope(context, future).await
+}
+
+/// Change context of the current task, if one was set
+pub fn current_change_context() -> Option<ChangeContext> {
+    CURRENT_CHANGE.try_with(|context| context.clone()).ok()
+}
+
+/// Apply the current task's change context to a connection as it leaves the
+/// pool. Every acquire overwrites the settings, so a version is never
+/// attributed to a previous request's user.
+pub async fn apply_change_context(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    let context = current_change_context().unwrap_or_default();
+
+    sqlx::query(
+        "SELECT set_config('app.user_id', $1, false), set_config('app.change_reason', $2, false), \
+         set_config('app.change_source', $3, false)",
+    )
+    .bind(context.user_id.map(|id| id.to_string()).unwrap_or_default())
+    .bind(context.reason.unwrap_or_default())
+    .bind(context.source.unwrap_or_default())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_change_context_scope() {
+        assert_eq!(current_change_context(), None);
+
+        let context = ChangeContext::new(Some(Uuid::new_v4()), Some("Corrected date of birth".to_string()))
+            .with_source("device:MX450-1234");
+        let seen = with_change_context(context.clone(), async { current_change_context() }).await;
+        assert_eq!(seen, Some(context));
+    }
+}
@@ -0,0 +1,105 @@
+use sqlx::PgConnection;
+use std::future::Future;
+use uuid::Uuid;
+
+/// Tenant every pre-tenancy row was assigned to. Requests that resolve no
+/// tenant run as this tenant, so single-clinic deployments need no setup.
+pub const DEFAULT_TENANT_ID: Uuid = Uuid::from_u128(1);
+
+tokio::task_local! {
+    static CURRENT_TENANT: TenantContext;
+}
+
+/// Tenant a unit of work runs as. Applied to pooled connections as Postgres
+/// session settings that the row-level-security policies read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantContext {
+    pub tenant_id: Uuid,
+    /// Dedicated schema for schema-per-tenant isolation
+    pub schema: Option<String>,
+    /// Platform jobs (cleanup, reporting) that must see every tenant
+    pub bypass: bool,
+}
+
+impl TenantContext {
+    pub fn new(tenant_id: Uuid, schema: Option<String>) -> Self {
+        Self {
+            tenant_id,
+            schema,
+            bypass: false,
+        }
+    }
+
+    /// Cross-tenant context for platform maintenance jobs
+    pub fn system() -> Self {
+        Self {
+            tenant_id: DEFAULT_TENANT_ID,
+            schema: None,
+            bypass: true,
+        }
+    }
+}
+
+/// Run a future as a tenant. Database work inside it, including work in
+/// nested calls, is isolated to that tenant. Tasks spawned from inside do
+/// not inherit the tenant and must be wrapped again.
+pub async fn with_tenant<F: Future>(context: TenantContext, future: F) -> F::Output {
+    CURRENT_TENANT.scope(context, future).await
+}
+
+/// Tenant of the current task, if one was set
+pub fn current_tenant() -> Option<TenantContext> {
+    CURRENT_TENANT.try_with(|context| context.clone()).ok()
+}
+
+/// Tenant ID of the current task, falling back to the default tenant
+pub fn current_tenant_id() -> Uuid {
+    current_tenant().map_or(DEFAULT_TENANT_ID, |context| context.tenant_id)
+}
+
+/// Apply the current task's tenant to a connection as it leaves the pool.
+/// Every acquire overwrites the settings, so a connection never carries a
+/// previous request's tenant.
+pub async fn apply_tenant(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    let context = current_tenant();
+    let tenant_id = context.as_ref().map(|c| c.tenant_id.to_string()).unwrap_or_default();
+    let bypass = if context.as_ref().is_some_and(|c| c.bypass) { "on" } else { "off" };
+    let search_path = match context.as_ref().and_then(|c| c.schema.as_deref()) {
+        Some(schema) => format!("{}, public", quote_identifier(schema)),
+        None => "public".to_string(),
+    };
+
+    sqlx::query(
+        "SELECT set_config('app.tenant_id', $1, false), set_config('app.bypass_tenant', $2, false), set_config('search_path', $3, false)",
+    )
+    .bind(tenant_id)
+    .bind(bypass)
+    .bind(search_path)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Quote a schema name for use in `search_path`
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tenant_scope() {
+        assert_eq!(current_tenant(), None);
+        assert_eq!(current_tenant_id(), DEFAULT_TENANT_ID);
+
+        let tenant_id = Uuid::new_v4();
+        let seen = with_tenant(TenantContext::new(tenant_id, Some("clinic_a".to_string())), async {
+            current_tenant_id()
+        })
+        .await;
+        assert_eq!(seen, tenant_id);
+        assert_eq!(quote_identifier("clinic\"a"), "\"clinic\"\"a\"");
+    }
+}
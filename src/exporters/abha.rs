@@ -0,0 +1,353 @@
+// ABHA (Ayushman Bharat Health Account) card QR codes: generated the way
+// ABDM prints them on ABHA cards, and read back from scans to prefill
+// patient registration
+
+use chrono::{Datelike, NaiveDate};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::core::HimsError;
+use crate::models::constants::{ABHA_ADDRESS_SYSTEM, ABHA_NUMBER_SYSTEM};
+use crate::models::{
+    Address, AddressUse, ContactPoint, ContactPointSystem, ContactPointUse, Gender, HumanName, Identifier, NameUse,
+    Patient,
+};
+
+/// Keys a field is found under in scanned payloads: the ABHA card's own
+/// first, then those of older Health ID cards and the ABHA app
+const NUMBER_KEYS: &[&str] = &["hidn", "abhaNumber", "abha_number", "healthIdNumber"];
+const ADDRESS_KEYS: &[&str] = &["hid", "phr", "abhaAddress", "abha_address", "healthId"];
+const NAME_KEYS: &[&str] = &["name", "fullName"];
+const GENDER_KEYS: &[&str] = &["gender", "sex"];
+const DOB_KEYS: &[&str] = &["dob", "dateOfBirth", "birthDate"];
+const YOB_KEYS: &[&str] = &["yob", "yearOfBirth"];
+const MOBILE_KEYS: &[&str] = &["mobile", "mobileNumber", "phone"];
+const POSTAL_ADDRESS_KEYS: &[&str] = &["address"];
+const DISTRICT_KEYS: &[&str] = &["district_name", "districtName", "district name"];
+const STATE_KEYS: &[&str] = &["state name", "state_name", "stateName"];
+const DISTRICT_LGD_KEYS: &[&str] = &["distlgd", "districtCode", "district_lgd"];
+const STATE_LGD_KEYS: &[&str] = &["statelgd", "stateCode", "state_lgd"];
+
+/// What an ABHA card's QR code carries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbhaCard {
+    /// 14-digit ABHA number, as `XX-XXXX-XXXX-XXXX`
+    pub abha_number: String,
+    /// ABHA address the patient signs in with, e.g. `name@abdm`
+    pub abha_address: Option<String>,
+    pub name: Option<String>,
+    pub gender: Option<Gender>,
+    pub date_of_birth: Option<NaiveDate>,
+    /// Year of birth, where only that was given at enrolment
+    pub year_of_birth: Option<i32>,
+    pub mobile: Option<String>,
+    pub address: Option<String>,
+    pub district_name: Option<String>,
+    pub state_name: Option<String>,
+    /// LGD (Local Government Directory) codes of the district and state
+    pub district_lgd: Option<String>,
+    pub state_lgd: Option<String>,
+}
+
+/// An ABHA number as `XX-XXXX-XXXX-XXXX`, from its 14 digits with or
+/// without separators
+pub fn format_abha_number(value: &str) -> Option<String> {
+    let digits: String = value.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+    if digits.len() != 14 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}-{}-{}-{}", &digits[0..2], &digits[2..6], &digits[6..10], &digits[10..14]))
+}
+
+impl AbhaCard {
+    /// Read a scanned QR payload: the JSON object of an ABHA card, or a bare
+    /// ABHA number
+    pub fn from_qr_payload(payload: &str) -> Result<Self, HimsError> {
+        let payload = payload.trim().trim_start_matches('\u{feff}');
+        if let Some(abha_number) = format_abha_number(payload) {
+            return Ok(Self::with_number(abha_number));
+        }
+
+        let fields = match serde_json::from_str::<Value>(payload) {
+            Ok(Value::Object(fields)) => fields,
+            _ => {
+                return Err(HimsError::ValidationError {
+                    message: "QR code is neither an ABHA card nor an ABHA number".to_string(),
+                })
+            }
+        };
+        let number = field(&fields, NUMBER_KEYS).ok_or_else(|| HimsError::ValidationError {
+            message: "QR code does not carry an ABHA number".to_string(),
+        })?;
+        let abha_number = format_abha_number(&number).ok_or_else(|| HimsError::ValidationError {
+            message: format!("{} is not a 14-digit ABHA number", number),
+        })?;
+
+        let date_of_birth = field(&fields, DOB_KEYS).and_then(|dob| parse_date(&dob));
+        let year_of_birth = field(&fields, YOB_KEYS)
+            .and_then(|yob| yob.parse().ok())
+            .or_else(|| date_of_birth.map(|dob| dob.year()));
+        Ok(Self {
+            abha_number,
+            abha_address: field(&fields, ADDRESS_KEYS),
+            name: field(&fields, NAME_KEYS),
+            gender: field(&fields, GENDER_KEYS).and_then(|gender| parse_gender(&gender)),
+            date_of_birth,
+            year_of_birth,
+            mobile: field(&fields, MOBILE_KEYS),
+            address: field(&fields, POSTAL_ADDRESS_KEYS),
+            district_name: field(&fields, DISTRICT_KEYS),
+            state_name: field(&fields, STATE_KEYS),
+            district_lgd: field(&fields, DISTRICT_LGD_KEYS),
+            state_lgd: field(&fields, STATE_LGD_KEYS),
+        })
+    }
+
+    /// The card of a registered patient, if they have an ABHA number
+    pub fn from_patient(patient: &Patient) -> Option<Self> {
+        let system_value = |system: &str| {
+            patient
+                .identifier
+                .iter()
+                .find(|identifier| identifier.system.as_deref() == Some(system))
+                .map(|identifier| identifier.value.clone())
+        };
+        let abha_number = format_abha_number(&system_value(ABHA_NUMBER_SYSTEM)?)?;
+
+        let name = patient.name.iter().find(|name| matches!(name.use_type, Some(NameUse::Official)));
+        let name = name.or_else(|| patient.name.first()).and_then(|name| {
+            name.text.clone().or_else(|| {
+                let parts: Vec<&str> = name.given.iter().map(String::as_str).chain(name.family.as_deref()).collect();
+                (!parts.is_empty()).then(|| parts.join(" "))
+            })
+        });
+        let mobile = patient
+            .telecom
+            .iter()
+            .find(|telecom| matches!(telecom.system, ContactPointSystem::Phone | ContactPointSystem::Sms))
+            .map(|telecom| telecom.value.clone());
+        let address = patient.address.first();
+
+        Some(Self {
+            abha_number,
+            abha_address: system_value(ABHA_ADDRESS_SYSTEM),
+            name,
+            gender: Some(patient.gender.clone()),
+            date_of_birth: patient.birth_date,
+            year_of_birth: patient.birth_date.map(|dob| dob.year()),
+            mobile,
+            address: address.and_then(|address| {
+                address.text.clone().or_else(|| (!address.line.is_empty()).then(|| address.line.join(", ")))
+            }),
+            district_name: address.and_then(|address| address.district.clone()),
+            state_name: address.and_then(|address| address.state.clone()),
+            district_lgd: None,
+            state_lgd: None,
+        })
+    }
+
+    fn with_number(abha_number: String) -> Self {
+        Self {
+            abha_number,
+            abha_address: None,
+            name: None,
+            gender: None,
+            date_of_birth: None,
+            year_of_birth: None,
+            mobile: None,
+            address: None,
+            district_name: None,
+            state_name: None,
+            district_lgd: None,
+            state_lgd: None,
+        }
+    }
+
+    /// The JSON object ABHA card QR codes carry, under the card's keys, with
+    /// the date of birth as `d-m-yyyy` and gender as `M`, `F` or `O`
+    pub fn to_qr_payload(&self) -> String {
+        let mut fields = Map::new();
+        let mut put = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                fields.insert(key.to_string(), Value::String(value));
+            }
+        };
+        put("hidn", Some(self.abha_number.clone()));
+        put("hid", self.abha_address.clone());
+        put("name", self.name.clone());
+        put("gender", self.gender.as_ref().map(|gender| gender_code(gender).to_string()));
+        put("statelgd", self.state_lgd.clone());
+        put("distlgd", self.district_lgd.clone());
+        put(
+            "dob",
+            self.date_of_birth.map(|dob| format!("{}-{}-{}", dob.day(), dob.month(), dob.year())),
+        );
+        if self.date_of_birth.is_none() {
+            put("yob", self.year_of_birth.map(|yob| yob.to_string()));
+        }
+        put("address", self.address.clone());
+        put("district_name", self.district_name.clone());
+        put("state name", self.state_name.clone());
+        put("mobile", self.mobile.clone());
+        Value::Object(fields).to_string()
+    }
+
+    /// The card's QR code as an SVG image
+    pub fn to_qr_svg(&self) -> Result<String, HimsError> {
+        let code = QrCode::with_error_correction_level(self.to_qr_payload().as_bytes(), EcLevel::M).map_err(|e| {
+            HimsError::InternalError {
+                message: format!("Failed to encode ABHA QR code: {}", e),
+            }
+        })?;
+        Ok(code.render::<svg::Color>().min_dimensions(256, 256).build())
+    }
+
+    /// Identifiers to register the patient under: the ABHA number, and the
+    /// ABHA address if the card has one
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        let mut identifiers = vec![Identifier {
+            use_type: Some("official".to_string()),
+            system: Some(ABHA_NUMBER_SYSTEM.to_string()),
+            value: self.abha_number.clone(),
+        }];
+        if let Some(abha_address) = &self.abha_address {
+            identifiers.push(Identifier {
+                use_type: Some("secondary".to_string()),
+                system: Some(ABHA_ADDRESS_SYSTEM.to_string()),
+                value: abha_address.clone(),
+            });
+        }
+        identifiers
+    }
+
+    /// The name on the card, its last word taken as the family name
+    pub fn human_name(&self) -> Option<HumanName> {
+        let text = self.name.as_deref()?.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut given: Vec<String> = text.split(' ').map(str::to_string).collect();
+        let family = if given.len() > 1 { given.pop() } else { None };
+        Some(HumanName {
+            use_type: Some(NameUse::Official),
+            text: Some(text),
+            family,
+            given,
+            prefix: Vec::new(),
+            suffix: Vec::new(),
+        })
+    }
+
+    pub fn telecom(&self) -> Option<ContactPoint> {
+        Some(ContactPoint {
+            system: ContactPointSystem::Phone,
+            value: self.mobile.clone()?,
+            use_type: Some(ContactPointUse::Mobile),
+            rank: Some(1),
+        })
+    }
+
+    pub fn postal_address(&self) -> Option<Address> {
+        if self.address.is_none() && self.district_name.is_none() && self.state_name.is_none() {
+            return None;
+        }
+        Some(Address {
+            use_type: Some(AddressUse::Home),
+            address_type: None,
+            text: self.address.clone(),
+            line: self.address.iter().cloned().collect(),
+            city: None,
+            district: self.district_name.clone(),
+            state: self.state_name.clone(),
+            postal_code: None,
+            country: Some("IN".to_string()),
+        })
+    }
+}
+
+/// The first non-empty value of a field, under any of its keys
+fn field(fields: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| {
+        let value = match fields.get(*key)? {
+            Value::String(value) => value.trim().to_string(),
+            Value::Number(value) => value.to_string(),
+            _ => return None,
+        };
+        (!value.is_empty()).then_some(value)
+    })
+}
+
+/// Dates as cards print them, `d-m-yyyy` or `d/m/yyyy`, or as `yyyy-mm-dd`
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    ["%d-%m-%Y", "%d/%m/%Y", "%Y-%m-%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+fn parse_gender(value: &str) -> Option<Gender> {
+    match value.to_ascii_uppercase().as_str() {
+        "M" | "MALE" => Some(Gender::Male),
+        "F" | "FEMALE" => Some(Gender::Female),
+        "O" | "T" | "OTHER" | "TRANSGENDER" => Some(Gender::Other),
+        "U" | "UNKNOWN" => Some(Gender::Unknown),
+        _ => None,
+    }
+}
+
+fn gender_code(gender: &Gender) -> &'static str {
+    match gender {
+        Gender::Male => "M",
+        Gender::Female => "F",
+        Gender::Other => "O",
+        Gender::Unknown => "U",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCANNED: &str = r#"{"hidn":"91-5012-3456-7890","hid":"asha.devi@abdm","name":"Asha Kumari Devi",
+        "gender":"F","statelgd":"29","distlgd":"572","dob":"7-3-1988","address":"12 MG Road, Indiranagar",
+        "district_name":"Bengaluru Urban","state name":"KARNATAKA","mobile":"9876543210"}"#;
+
+    #[test]
+    fn scanned_cards_fill_in_registration() {
+        let card = AbhaCard::from_qr_payload(SCANNED).unwrap();
+        assert_eq!(card.abha_number, "91-5012-3456-7890");
+        assert_eq!(card.date_of_birth, NaiveDate::from_ymd_opt(1988, 3, 7));
+        assert_eq!(card.year_of_birth, Some(1988));
+        assert!(matches!(card.gender, Some(Gender::Female)));
+        assert_eq!(card.state_name.as_deref(), Some("KARNATAKA"));
+
+        let identifiers = card.identifiers();
+        assert_eq!(identifiers.len(), 2);
+        assert_eq!(identifiers[1].value, "asha.devi@abdm");
+        let name = card.human_name().unwrap();
+        assert_eq!(name.family.as_deref(), Some("Devi"));
+        assert_eq!(name.given, vec!["Asha", "Kumari"]);
+        assert_eq!(card.telecom().unwrap().value, "9876543210");
+
+        let bare = AbhaCard::from_qr_payload(" 91501234567890\n").unwrap();
+        assert_eq!(bare.abha_number, "91-5012-3456-7890");
+        assert!(bare.human_name().is_none());
+        assert!(AbhaCard::from_qr_payload(r#"{"hidn":"12-34"}"#).is_err());
+        assert!(AbhaCard::from_qr_payload("https://example.org").is_err());
+    }
+
+    #[test]
+    fn generated_payloads_read_back() {
+        let card = AbhaCard::from_qr_payload(SCANNED).unwrap();
+        let payload = card.to_qr_payload();
+        let fields: Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(fields["dob"], "7-3-1988");
+        assert_eq!(fields["gender"], "F");
+
+        let again = AbhaCard::from_qr_payload(&payload).unwrap();
+        assert_eq!(again.abha_address, card.abha_address);
+        assert_eq!(again.date_of_birth, card.date_of_birth);
+        assert_eq!(again.district_lgd.as_deref(), Some("572"));
+
+        let svg = card.to_qr_svg().unwrap();
+        assert!(svg.contains("<svg"));
+    }
+}
@@ -1,15 +1,1336 @@
-pub struct ApiAdapter;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, NaiveDate, Utc};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::header::{HeaderMap, ACCEPT, CONTENT_TYPE, LOCATION};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
 
-impl ApiAdapter {
-    pub fn sync_with_external_system(_data: &str) -> Result<String, crate::core::HimsError> {
-        Ok("External system sync complete".to_string()) // Placeholder
+use crate::core::HimsError;
+use crate::countries::residency::{DataRegion, DataResidencyPolicy, DestinationKind, ExportDestination, ResidencyDecision};
+
+const FHIR_JSON: &str = "application/fhir+json";
+
+/// Timeout of each request to a vendor API
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tokens are renewed this long before they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// EHR vendor an adapter talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vendor {
+    Epic,
+    Cerner,
+    Athena,
+    /// Any FHIR R4 server
+    Fhir,
+}
+
+impl Vendor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Vendor::Epic => "epic",
+            Vendor::Cerner => "cerner",
+            Vendor::Athena => "athena",
+            Vendor::Fhir => "fhir",
+        }
+    }
+}
+
+/// Something a remote system may or may not support. Callers check for it
+/// with [`AdapterCapabilities::supports`] before relying on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// `GET Patient/{id}`
+    PatientRead,
+    /// `GET Patient?...` by demographics
+    PatientSearch,
+    /// `GET Patient?identifier=...`
+    PatientSearchByIdentifier,
+    /// `POST DocumentReference`
+    DocumentPush,
+    /// `GET DocumentReference?patient=...`
+    DocumentSearch,
+    /// `Patient/$export` or `Group/[id]/$export`
+    BulkExport,
+    /// SMART Backend Services (asymmetric client authentication)
+    SmartBackendServices,
+}
+
+/// What a remote system supports, from its CapabilityStatement and SMART
+/// configuration
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdapterCapabilities {
+    pub fhir_version: Option<String>,
+    /// Software name and version the server reports
+    pub software: Option<String>,
+    pub capabilities: BTreeSet<Capability>,
+    /// Interactions supported per resource type, e.g. `Patient: [read, search-type]`
+    pub resources: BTreeMap<String, Vec<String>>,
+    pub token_endpoint: Option<String>,
+    pub discovered_at: Option<DateTime<Utc>>,
+}
+
+impl AdapterCapabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Whether a resource type supports an interaction, e.g. `create`
+    pub fn supports_interaction(&self, resource_type: &str, interaction: &str) -> bool {
+        self.resources
+            .get(resource_type)
+            .is_some_and(|interactions| interactions.iter().any(|code| code == interaction))
+    }
+
+    /// Read a CapabilityStatement and, when the server has one, its
+    /// `.well-known/smart-configuration`
+    pub fn from_statement(statement: &Value, smart_configuration: Option<&Value>) -> Self {
+        let mut capabilities = Self {
+            fhir_version: statement["fhirVersion"].as_str().map(str::to_string),
+            software: statement["software"]["name"].as_str().map(|name| match statement["software"]["version"].as_str() {
+                Some(version) => format!("{} {}", name, version),
+                None => name.to_string(),
+            }),
+            discovered_at: Some(Utc::now()),
+            ..Self::default()
+        };
+
+        let server = statement["rest"]
+            .as_array()
+            .and_then(|rest| rest.iter().find(|rest| rest["mode"] == "server"))
+            .cloned()
+            .unwrap_or(Value::Null);
+        let empty = Vec::new();
+        for resource in server["resource"].as_array().unwrap_or(&empty) {
+            let Some(resource_type) = resource["type"].as_str() else {
+                continue;
+            };
+            let interactions: Vec<String> = resource["interaction"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .filter_map(|interaction| interaction["code"].as_str().map(str::to_string))
+                .collect();
+            let search_params: Vec<&str> = resource["searchParam"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .filter_map(|param| param["name"].as_str())
+                .collect();
+            let has = |code: &str| interactions.iter().any(|interaction| interaction == code);
+
+            match resource_type {
+                "Patient" => {
+                    if has("read") {
+                        capabilities.capabilities.insert(Capability::PatientRead);
+                    }
+                    if has("search-type") {
+                        capabilities.capabilities.insert(Capability::PatientSearch);
+                        if search_params.contains(&"identifier") {
+                            capabilities.capabilities.insert(Capability::PatientSearchByIdentifier);
+                        }
+                    }
+                }
+                "DocumentReference" => {
+                    if has("create") {
+                        capabilities.capabilities.insert(Capability::DocumentPush);
+                    }
+                    if has("search-type") {
+                        capabilities.capabilities.insert(Capability::DocumentSearch);
+                    }
+                }
+                _ => {}
+            }
+            if resource["operation"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .any(|operation| operation["name"] == "export")
+            {
+                capabilities.capabilities.insert(Capability::BulkExport);
+            }
+            capabilities.resources.insert(resource_type.to_string(), interactions);
+        }
+        if server["operation"]
+            .as_array()
+            .unwrap_or(&empty)
+            .iter()
+            .any(|operation| operation["name"] == "export")
+        {
+            capabilities.capabilities.insert(Capability::BulkExport);
+        }
+
+        // The SMART configuration wins over the oauth-uris extension
+        capabilities.token_endpoint = smart_configuration
+            .and_then(|configuration| configuration["token_endpoint"].as_str())
+            .map(str::to_string)
+            .or_else(|| {
+                server["security"]["extension"]
+                    .as_array()?
+                    .iter()
+                    .find(|extension| extension["url"].as_str().is_some_and(|url| url.ends_with("oauth-uris")))?["extension"]
+                    .as_array()?
+                    .iter()
+                    .find(|uri| uri["url"] == "token")?["valueUri"]
+                    .as_str()
+                    .map(str::to_string)
+            });
+        let asymmetric = smart_configuration
+            .and_then(|configuration| configuration["token_endpoint_auth_methods_supported"].as_array())
+            .is_some_and(|methods| methods.iter().any(|method| method == "private_key_jwt"));
+        if asymmetric {
+            capabilities.capabilities.insert(Capability::SmartBackendServices);
+        }
+        capabilities
+    }
+}
+
+/// How an adapter obtains access tokens
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdapterAuth {
+    /// Open endpoints, e.g. public test servers
+    None,
+    /// A long-lived token issued out of band
+    Bearer { token: String },
+    /// OAuth 2.0 client credentials grant, the secret sent with HTTP Basic
+    /// authentication
+    ClientCredentials {
+        token_url: String,
+        client_id: String,
+        client_secret: String,
+        #[serde(default)]
+        scope: Option<String>,
+    },
+    /// SMART Backend Services: client credentials grant authenticated with
+    /// an RS384-signed JWT
+    BackendServices {
+        token_url: String,
+        client_id: String,
+        /// PEM-encoded RSA private key registered with the vendor
+        private_key_pem: String,
+        /// Key ID of the registered public key, sent as the JWT `kid`
+        #[serde(default)]
+        key_id: Option<String>,
+        #[serde(default)]
+        scope: Option<String>,
+    },
+}
+
+impl std::fmt::Debug for AdapterAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdapterAuth::None => f.write_str("None"),
+            AdapterAuth::Bearer { .. } => f.write_str("Bearer"),
+            AdapterAuth::ClientCredentials { token_url, client_id, .. } => f
+                .debug_struct("ClientCredentials")
+                .field("token_url", token_url)
+                .field("client_id", client_id)
+                .finish(),
+            AdapterAuth::BackendServices { token_url, client_id, .. } => f
+                .debug_struct("BackendServices")
+                .field("token_url", token_url)
+                .field("client_id", client_id)
+                .finish(),
+        }
+    }
+}
+
+/// Everything needed to build an adapter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdapterConfig {
+    pub vendor: Vendor,
+    /// Name used in logs and residency checks
+    pub name: String,
+    /// FHIR R4 base URL, e.g. `https://fhir.epic.com/interconnect-fhir-oauth/api/FHIR/R4`
+    pub base_url: String,
+    pub auth: AdapterAuth,
+    /// Where the vendor stores data, checked against the residency policy
+    /// before anything is pushed
+    #[serde(default)]
+    pub region: Option<DataRegion>,
+    /// athenahealth practice, sent as `ah-practice` on every request,
+    /// e.g. `Organization/a-1.Practice-195900`
+    #[serde(default)]
+    pub practice: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub value: String,
+    pub expires_at: Instant,
+}
+
+/// Patient lookup criteria. Vendors differ in which combinations they
+/// accept; an identifier is accepted everywhere.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PatientQuery {
+    /// Identifier system URI, e.g. the vendor's MRN system
+    #[serde(default)]
+    pub identifier_system: Option<String>,
+    #[serde(default)]
+    pub identifier: Option<String>,
+    #[serde(default)]
+    pub family: Option<String>,
+    #[serde(default)]
+    pub given: Option<String>,
+    #[serde(default)]
+    pub birth_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub gender: Option<String>,
+}
+
+impl PatientQuery {
+    fn parameters(&self) -> Vec<(String, String)> {
+        let mut parameters = Vec::new();
+        if let Some(value) = &self.identifier {
+            let token = match &self.identifier_system {
+                Some(system) => format!("{}|{}", system, value),
+                None => value.clone(),
+            };
+            parameters.push(("identifier".to_string(), token));
+        }
+        for (name, value) in [("family", &self.family), ("given", &self.given), ("gender", &self.gender)] {
+            if let Some(value) = value {
+                parameters.push((name.to_string(), value.clone()));
+            }
+        }
+        if let Some(birth_date) = self.birth_date {
+            parameters.push(("birthdate".to_string(), birth_date.to_string()));
+        }
+        parameters
+    }
+}
+
+/// A clinical document to file in the remote chart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentPush {
+    /// Patient ID on the remote system
+    pub patient_id: String,
+    /// Encounter ID on the remote system; Epic requires one for notes
+    #[serde(default)]
+    pub encounter_id: Option<String>,
+    /// LOINC document type, e.g. `11506-3` (progress note)
+    pub type_code: String,
+    pub type_display: String,
+    pub title: String,
+    /// MIME type of the content, e.g. `text/plain` or `application/pdf`
+    pub content_type: String,
+    pub content: Vec<u8>,
+    pub created: DateTime<Utc>,
+}
+
+impl DocumentPush {
+    /// FHIR R4 DocumentReference for the document
+    pub fn to_document_reference(&self) -> Value {
+        let mut document = json!({
+            "resourceType": "DocumentReference",
+            "status": "current",
+            "docStatus": "final",
+            "type": {
+                "coding": [{ "system": "http://loinc.org", "code": self.type_code, "display": self.type_display }],
+                "text": self.type_display
+            },
+            "category": [{
+                "coding": [{
+                    "system": "http://hl7.org/fhir/us/core/CodeSystem/us-core-documentreference-category",
+                    "code": "clinical-note"
+                }]
+            }],
+            "subject": { "reference": format!("Patient/{}", self.patient_id) },
+            "date": self.created.to_rfc3339(),
+            "description": self.title,
+            "content": [{
+                "attachment": {
+                    "contentType": self.content_type,
+                    "data": general_purpose::STANDARD.encode(&self.content),
+                    "title": self.title,
+                    "creation": self.created.to_rfc3339()
+                }
+            }]
+        });
+        if let Some(encounter_id) = &self.encounter_id {
+            document["context"] = json!({
+                "encounter": [{ "reference": format!("Encounter/{}", encounter_id) }],
+                "period": { "start": self.created.to_rfc3339() }
+            });
+        }
+        document
+    }
+}
+
+/// Where a pushed document landed
+#[derive(Debug, Clone, Serialize)]
+pub struct PushedDocument {
+    /// ID the vendor assigned, when it said
+    pub id: Option<String>,
+    pub location: Option<String>,
+}
+
+/// An EHR vendor's API. Adapters authenticate themselves, so callers only
+/// look patients up and push documents, after checking
+/// [`VendorAdapter::capabilities`] for what the remote system supports.
+#[async_trait]
+pub trait VendorAdapter: Send + Sync {
+    fn vendor(&self) -> Vendor;
+
+    fn config(&self) -> &AdapterConfig;
+
+    /// Access token for the next request, fetched or renewed as needed.
+    /// `None` for endpoints without authentication.
+    async fn authenticate(&self) -> Result<Option<String>, HimsError>;
+
+    /// What the remote system supports, discovered on first use and cached
+    async fn capabilities(&self) -> Result<AdapterCapabilities, HimsError>;
+
+    /// Discover the remote system's capabilities again
+    async fn discover(&self) -> Result<AdapterCapabilities, HimsError>;
+
+    async fn read_patient(&self, id: &str) -> Result<Option<Value>, HimsError>;
+
+    /// Patients matching the query, as FHIR Patient resources
+    async fn find_patients(&self, query: &PatientQuery) -> Result<Vec<Value>, HimsError>;
+
+    /// File a document in the patient's remote chart
+    async fn push_document(&self, document: &DocumentPush) -> Result<PushedDocument, HimsError>;
+}
+
+/// Build the adapter for a vendor
+pub fn adapter_for(config: AdapterConfig) -> Result<Box<dyn VendorAdapter>, HimsError> {
+    Ok(match config.vendor {
+        Vendor::Epic => Box::new(EpicAdapter::new(config)?),
+        Vendor::Cerner => Box::new(CernerAdapter::new(config)?),
+        Vendor::Athena => Box::new(AthenaAdapter::new(config)?),
+        Vendor::Fhir => Box::new(FhirServerAdapter::new(config)?),
+    })
+}
+
+/// Push a document only if the residency policy lets data reach the
+/// vendor's region
+pub async fn push_document_checked(
+    adapter: &dyn VendorAdapter,
+    policy: &DataResidencyPolicy,
+    document: &DocumentPush,
+) -> Result<PushedDocument, HimsError> {
+    let config = adapter.config();
+    let region = config.region.clone().ok_or_else(|| HimsError::ConfigurationError {
+        message: format!("Adapter {} has no data region to check residency against", config.name),
+    })?;
+    let destination = ExportDestination {
+        name: config.name.clone(),
+        kind: DestinationKind::ApiAdapter,
+        region,
+    };
+
+    let decision = policy.check_export(&destination);
+    if let ResidencyDecision::Warned(violation) = &decision {
+        tracing::warn!("Data residency violation: {}", violation.message);
+    }
+    decision.into_result()?;
+    adapter.push_document(document).await
+}
+
+/// FHIR R4 REST access shared by the adapters: token handling, capability
+/// discovery and the reads and writes every vendor supports in some form
+pub struct FhirApi {
+    config: AdapterConfig,
+    http: reqwest::Client,
+    token: Mutex<Option<AccessToken>>,
+    capabilities: RwLock<Option<AdapterCapabilities>>,
+}
+
+impl FhirApi {
+    pub fn new(config: AdapterConfig) -> Result<Self, HimsError> {
+        if !config.base_url.starts_with("https://") && !config.base_url.starts_with("http://") {
+            return Err(HimsError::ConfigurationError {
+                message: format!("Adapter {} needs an http(s) base URL", config.name),
+            });
+        }
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| HimsError::ConfigurationError { message: e.to_string() })?;
+        Ok(Self {
+            config: AdapterConfig {
+                base_url: config.base_url.trim_end_matches('/').to_string(),
+                ..config
+            },
+            http,
+            token: Mutex::new(None),
+            capabilities: RwLock::new(None),
+        })
+    }
+
+    pub fn config(&self) -> &AdapterConfig {
+        &self.config
+    }
+
+    pub async fn token(&self) -> Result<Option<String>, HimsError> {
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref().filter(|token| token.expires_at > Instant::now()) {
+            return Ok(Some(token.value.clone()));
+        }
+
+        let request = match &self.config.auth {
+            AdapterAuth::None => return Ok(None),
+            AdapterAuth::Bearer { token } => return Ok(Some(token.clone())),
+            AdapterAuth::ClientCredentials { token_url, client_id, client_secret, scope } => {
+                let mut form = vec![("grant_type", "client_credentials".to_string())];
+                if let Some(scope) = scope {
+                    form.push(("scope", scope.clone()));
+                }
+                self.http.post(token_url).basic_auth(client_id, Some(client_secret)).form(&form)
+            }
+            AdapterAuth::BackendServices { token_url, client_id, private_key_pem, key_id, scope } => {
+                let assertion = Self::client_assertion(token_url, client_id, private_key_pem, key_id.as_deref())?;
+                let mut form = vec![
+                    ("grant_type", "client_credentials".to_string()),
+                    ("client_assertion_type", "urn:ietf:params:oauth:client-assertion-type:jwt-bearer".to_string()),
+                    ("client_assertion", assertion),
+                ];
+                if let Some(scope) = scope {
+                    form.push(("scope", scope.clone()));
+                }
+                self.http.post(token_url).form(&form)
+            }
+        };
+
+        let response = request.send().await.map_err(|e| HimsError::NetworkError {
+            message: format!("Token request for {} failed: {}", self.config.name, e),
+        })?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(HimsError::AuthenticationError {
+                message: format!("{} token endpoint answered {}: {}", self.config.name, status, body),
+            });
+        }
+        let body: Value = response.json().await.map_err(|e| HimsError::AuthenticationError {
+            message: format!("Invalid token response from {}: {}", self.config.name, e),
+        })?;
+        let value = body["access_token"].as_str().ok_or_else(|| HimsError::AuthenticationError {
+            message: format!("Token response from {} has no access_token", self.config.name),
+        })?;
+        let lifetime = Duration::from_secs(body["expires_in"].as_u64().unwrap_or(300));
+
+        let token = AccessToken {
+            value: value.to_string(),
+            expires_at: Instant::now() + lifetime.saturating_sub(TOKEN_EXPIRY_MARGIN),
+        };
+        *cached = Some(token.clone());
+        Ok(Some(token.value))
+    }
+
+    /// Signed JWT a SMART Backend Services client authenticates with
+    fn client_assertion(token_url: &str, client_id: &str, private_key_pem: &str, key_id: Option<&str>) -> Result<String, HimsError> {
+        let key = EncodingKey::from_rsa_pem(private_key_pem.as_bytes()).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Invalid backend services private key: {}", e),
+        })?;
+        let header = Header {
+            kid: key_id.map(str::to_string),
+            ..Header::new(Algorithm::RS384)
+        };
+        let claims = json!({
+            "iss": client_id,
+            "sub": client_id,
+            "aud": token_url,
+            "jti": Uuid::new_v4().to_string(),
+            "exp": (Utc::now() + chrono::Duration::minutes(5)).timestamp(),
+        });
+        encode(&header, &claims, &key).map_err(|e| HimsError::InternalError {
+            message: format!("Failed to sign client assertion: {}", e),
+        })
     }
-    
-    pub fn sync_with_epic(_data: &str) -> Result<String, crate::core::HimsError> {
-        Ok("Epic integration complete".to_string()) // Placeholder
+
+    /// Send a request; the response status, headers and JSON body (if any)
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(String, String)],
+        body: Option<&Value>,
+    ) -> Result<(StatusCode, HeaderMap, Option<Value>), HimsError> {
+        let url = if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.config.base_url, path.trim_start_matches('/'))
+        };
+        let mut request = self.http.request(method, url).header(ACCEPT, FHIR_JSON).query(query);
+        if let Some(practice) = &self.config.practice {
+            request = request.query(&[("ah-practice", practice)]);
+        }
+        if let Some(token) = self.token().await? {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.header(CONTENT_TYPE, FHIR_JSON).body(body.to_string());
+        }
+
+        let response = request.send().await.map_err(|e| HimsError::NetworkError {
+            message: format!("Request to {} failed: {}", self.config.name, e),
+        })?;
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED {
+            // Revoked early; the next request fetches a new one
+            *self.token.lock().await = None;
+        }
+        let headers = response.headers().clone();
+        let text = response.text().await.unwrap_or_default();
+        let json = if text.trim().is_empty() { None } else { serde_json::from_str(&text).ok() };
+        Ok((status, headers, json))
+    }
+
+    /// Error for an unsuccessful response, with the OperationOutcome's
+    /// diagnostics when the server sent one
+    fn failure(&self, action: &str, status: StatusCode, body: Option<&Value>) -> HimsError {
+        let detail = body
+            .and_then(|outcome| outcome["issue"].as_array())
+            .map(|issues| {
+                issues
+                    .iter()
+                    .filter_map(|issue| issue["diagnostics"].as_str().or_else(|| issue["details"]["text"].as_str()))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .unwrap_or_default();
+        let message = format!("{} {} failed with {} {}", self.config.name, action, status, detail).trim_end().to_string();
+        match status {
+            StatusCode::UNAUTHORIZED => HimsError::AuthenticationError { message },
+            StatusCode::FORBIDDEN => HimsError::SecurityError { message },
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => HimsError::ValidationError { message },
+            _ => HimsError::NetworkError { message },
+        }
+    }
+
+    pub async fn capabilities(&self) -> Result<AdapterCapabilities, HimsError> {
+        let cached = self.capabilities.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        match cached {
+            Some(capabilities) => Ok(capabilities),
+            None => self.discover().await,
+        }
+    }
+
+    /// Read `metadata` and `.well-known/smart-configuration`. Neither needs
+    /// a token, so discovery works before credentials are set up.
+    pub async fn discover(&self) -> Result<AdapterCapabilities, HimsError> {
+        let get = |path: &str| {
+            self.http
+                .get(format!("{}/{}", self.config.base_url, path))
+                .header(ACCEPT, "application/fhir+json, application/json")
+                .send()
+        };
+
+        let response = get("metadata").await.map_err(|e| HimsError::NetworkError {
+            message: format!("Capability discovery for {} failed: {}", self.config.name, e),
+        })?;
+        if !response.status().is_success() {
+            return Err(HimsError::NetworkError {
+                message: format!("{} metadata answered {}", self.config.name, response.status()),
+            });
+        }
+        let statement: Value = response.json().await.map_err(|e| HimsError::FhirError {
+            message: format!("Invalid CapabilityStatement from {}: {}", self.config.name, e),
+        })?;
+        let smart_configuration = match get(".well-known/smart-configuration").await {
+            Ok(response) if response.status().is_success() => response.json::<Value>().await.ok(),
+            _ => None,
+        };
+
+        let capabilities = AdapterCapabilities::from_statement(&statement, smart_configuration.as_ref());
+        *self.capabilities.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// Fail early when the server said it cannot do something
+    pub async fn require(&self, capability: Capability) -> Result<(), HimsError> {
+        if self.capabilities().await?.supports(capability) {
+            Ok(())
+        } else {
+            Err(HimsError::ValidationError {
+                message: format!("{} does not support {:?}", self.config.name, capability),
+            })
+        }
+    }
+
+    pub async fn read(&self, resource_type: &str, id: &str) -> Result<Option<Value>, HimsError> {
+        let (status, _, body) = self.request(Method::GET, &format!("{}/{}", resource_type, id), &[], None).await?;
+        match status {
+            status if status.is_success() => Ok(body),
+            StatusCode::NOT_FOUND | StatusCode::GONE => Ok(None),
+            status => Err(self.failure(&format!("{} read", resource_type), status, body.as_ref())),
+        }
+    }
+
+    /// Resources of a search, following `next` links up to `max_pages`
+    pub async fn search(&self, resource_type: &str, parameters: &[(String, String)], max_pages: usize) -> Result<Vec<Value>, HimsError> {
+        let mut resources = Vec::new();
+        let mut next = Some((resource_type.to_string(), parameters.to_vec()));
+
+        for _ in 0..max_pages {
+            let Some((path, query)) = next.take() else {
+                break;
+            };
+            let (status, _, body) = self.request(Method::GET, &path, &query, None).await?;
+            if !status.is_success() {
+                return Err(self.failure(&format!("{} search", resource_type), status, body.as_ref()));
+            }
+            let bundle = body.unwrap_or(Value::Null);
+            let empty = Vec::new();
+            for entry in bundle["entry"].as_array().unwrap_or(&empty) {
+                // Search sets may also carry OperationOutcomes and includes
+                if entry["resource"]["resourceType"] == resource_type {
+                    resources.push(entry["resource"].clone());
+                }
+            }
+            next = bundle["link"]
+                .as_array()
+                .and_then(|links| links.iter().find(|link| link["relation"] == "next"))
+                .and_then(|link| link["url"].as_str())
+                .map(|url| (url.to_string(), Vec::new()));
+        }
+        Ok(resources)
+    }
+
+    pub async fn create(&self, resource: &Value) -> Result<PushedDocument, HimsError> {
+        let resource_type = resource["resourceType"].as_str().unwrap_or_default();
+        let (status, headers, body) = self.request(Method::POST, resource_type, &[], Some(resource)).await?;
+        if !status.is_success() {
+            return Err(self.failure(&format!("{} create", resource_type), status, body.as_ref()));
+        }
+
+        // Some servers answer 201 with only a Location header
+        let location = headers.get(LOCATION).and_then(|value| value.to_str().ok()).map(str::to_string);
+        let id = body
+            .as_ref()
+            .and_then(|resource| resource["id"].as_str().map(str::to_string))
+            .or_else(|| {
+                let location = location.as_deref()?;
+                let path = location.split("/_history/").next()?;
+                path.rsplit('/').next().map(str::to_string)
+            });
+        Ok(PushedDocument { id, location })
+    }
+}
+
+/// Epic on FHIR. Backend apps authenticate with SMART Backend Services;
+/// clinical notes are filed with DocumentReference.Create, which needs an
+/// encounter.
+pub struct EpicAdapter {
+    api: FhirApi,
+}
+
+impl EpicAdapter {
+    pub fn new(config: AdapterConfig) -> Result<Self, HimsError> {
+        if !matches!(config.auth, AdapterAuth::BackendServices { .. } | AdapterAuth::Bearer { .. }) {
+            return Err(HimsError::ConfigurationError {
+                message: "Epic adapters authenticate with SMART Backend Services".to_string(),
+            });
+        }
+        Ok(Self { api: FhirApi::new(config)? })
+    }
+}
+
+#[async_trait]
+impl VendorAdapter for EpicAdapter {
+    fn vendor(&self) -> Vendor {
+        Vendor::Epic
+    }
+
+    fn config(&self) -> &AdapterConfig {
+        self.api.config()
+    }
+
+    async fn authenticate(&self) -> Result<Option<String>, HimsError> {
+        self.api.token().await
+    }
+
+    async fn capabilities(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.capabilities().await
+    }
+
+    async fn discover(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.discover().await
+    }
+
+    async fn read_patient(&self, id: &str) -> Result<Option<Value>, HimsError> {
+        self.api.read("Patient", id).await
+    }
+
+    async fn find_patients(&self, query: &PatientQuery) -> Result<Vec<Value>, HimsError> {
+        // Epic only matches on an identifier or on name with birth date
+        let demographic = query.family.is_some() && query.given.is_some() && query.birth_date.is_some();
+        if query.identifier.is_none() && !demographic {
+            return Err(HimsError::ValidationError {
+                message: "Epic patient search needs an identifier, or family, given and birth date".to_string(),
+            });
+        }
+        self.api.search("Patient", &query.parameters(), 1).await
+    }
+
+    async fn push_document(&self, document: &DocumentPush) -> Result<PushedDocument, HimsError> {
+        if document.encounter_id.is_none() {
+            return Err(HimsError::ValidationError {
+                message: "Epic files clinical notes against an encounter".to_string(),
+            });
+        }
+        self.api.require(Capability::DocumentPush).await?;
+        self.api.create(&document.to_document_reference()).await
+    }
+}
+
+/// Oracle Health (Cerner) Millennium. System accounts use the client
+/// credentials grant; the tenant is part of the base URL.
+pub struct CernerAdapter {
+    api: FhirApi,
+}
+
+impl CernerAdapter {
+    pub fn new(config: AdapterConfig) -> Result<Self, HimsError> {
+        Ok(Self { api: FhirApi::new(config)? })
+    }
+}
+
+#[async_trait]
+impl VendorAdapter for CernerAdapter {
+    fn vendor(&self) -> Vendor {
+        Vendor::Cerner
+    }
+
+    fn config(&self) -> &AdapterConfig {
+        self.api.config()
     }
-    
-    pub fn sync_with_cerner(_data: &str) -> Result<String, crate::core::HimsError> {
-        Ok("Cerner integration complete".to_string()) // Placeholder
+
+    async fn authenticate(&self) -> Result<Option<String>, HimsError> {
+        self.api.token().await
+    }
+
+    async fn capabilities(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.capabilities().await
+    }
+
+    async fn discover(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.discover().await
+    }
+
+    async fn read_patient(&self, id: &str) -> Result<Option<Value>, HimsError> {
+        self.api.read("Patient", id).await
+    }
+
+    async fn find_patients(&self, query: &PatientQuery) -> Result<Vec<Value>, HimsError> {
+        self.api.search("Patient", &query.parameters(), 5).await
+    }
+
+    async fn push_document(&self, document: &DocumentPush) -> Result<PushedDocument, HimsError> {
+        self.api.require(Capability::DocumentPush).await?;
+        // Millennium wants the author time as a period even without an
+        // encounter
+        let mut resource = document.to_document_reference();
+        if resource.get("context").is_none() {
+            resource["context"] = json!({ "period": { "start": document.created.to_rfc3339() } });
+        }
+        self.api.create(&resource).await
+    }
+}
+
+/// athenahealth athenaOne. Every request names the practice it is for.
+pub struct AthenaAdapter {
+    api: FhirApi,
+}
+
+impl AthenaAdapter {
+    pub fn new(config: AdapterConfig) -> Result<Self, HimsError> {
+        if config.practice.is_none() {
+            return Err(HimsError::ConfigurationError {
+                message: "athenahealth adapters need the practice (ah-practice) they act for".to_string(),
+            });
+        }
+        Ok(Self { api: FhirApi::new(config)? })
+    }
+}
+
+#[async_trait]
+impl VendorAdapter for AthenaAdapter {
+    fn vendor(&self) -> Vendor {
+        Vendor::Athena
+    }
+
+    fn config(&self) -> &AdapterConfig {
+        self.api.config()
+    }
+
+    async fn authenticate(&self) -> Result<Option<String>, HimsError> {
+        self.api.token().await
+    }
+
+    async fn capabilities(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.capabilities().await
+    }
+
+    async fn discover(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.discover().await
+    }
+
+    async fn read_patient(&self, id: &str) -> Result<Option<Value>, HimsError> {
+        self.api.read("Patient", id).await
+    }
+
+    async fn find_patients(&self, query: &PatientQuery) -> Result<Vec<Value>, HimsError> {
+        self.api.search("Patient", &query.parameters(), 5).await
+    }
+
+    async fn push_document(&self, document: &DocumentPush) -> Result<PushedDocument, HimsError> {
+        self.api.require(Capability::DocumentPush).await?;
+        self.api.create(&document.to_document_reference()).await
+    }
+}
+
+/// Any FHIR R4 server, relying on discovery for what it supports
+pub struct FhirServerAdapter {
+    api: FhirApi,
+}
+
+impl FhirServerAdapter {
+    pub fn new(config: AdapterConfig) -> Result<Self, HimsError> {
+        Ok(Self { api: FhirApi::new(config)? })
+    }
+}
+
+#[async_trait]
+impl VendorAdapter for FhirServerAdapter {
+    fn vendor(&self) -> Vendor {
+        Vendor::Fhir
+    }
+
+    fn config(&self) -> &AdapterConfig {
+        self.api.config()
+    }
+
+    async fn authenticate(&self) -> Result<Option<String>, HimsError> {
+        self.api.token().await
+    }
+
+    async fn capabilities(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.capabilities().await
+    }
+
+    async fn discover(&self) -> Result<AdapterCapabilities, HimsError> {
+        self.api.discover().await
+    }
+
+    async fn read_patient(&self, id: &str) -> Result<Option<Value>, HimsError> {
+        self.api.require(Capability::PatientRead).await?;
+        self.api.read("Patient", id).await
+    }
+
+    async fn find_patients(&self, query: &PatientQuery) -> Result<Vec<Value>, HimsError> {
+        let capability = if query.identifier.is_some() {
+            Capability::PatientSearchByIdentifier
+        } else {
+            Capability::PatientSearch
+        };
+        self.api.require(capability).await?;
+        self.api.search("Patient", &query.parameters(), 5).await
+    }
+
+    async fn push_document(&self, document: &DocumentPush) -> Result<PushedDocument, HimsError> {
+        self.api.require(Capability::DocumentPush).await?;
+        self.api.create(&document.to_document_reference()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{
+        basic_auth, bearer_token, body_partial_json, body_string_contains, header, method, path, query_param,
+    };
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// RSA key registered for the test Epic client; used for nothing else
+    const BACKEND_SERVICES_KEY: &str = include_str!("testdata/backend_services_key.pem");
+
+    fn config(vendor: Vendor, server: &MockServer, auth: AdapterAuth) -> AdapterConfig {
+        AdapterConfig {
+            vendor,
+            name: vendor.as_str().to_string(),
+            base_url: format!("{}/fhir/", server.uri()),
+            auth,
+            region: None,
+            practice: None,
+        }
+    }
+
+    fn client_credentials(server: &MockServer) -> AdapterAuth {
+        AdapterAuth::ClientCredentials {
+            token_url: format!("{}/token", server.uri()),
+            client_id: "hims".to_string(),
+            client_secret: "s3cret".to_string(),
+            scope: Some("system/Patient.read system/DocumentReference.write".to_string()),
+        }
+    }
+
+    fn statement(document_push: bool) -> Value {
+        let mut document_interactions = vec![json!({ "code": "search-type" })];
+        if document_push {
+            document_interactions.push(json!({ "code": "create" }));
+        }
+        json!({
+            "resourceType": "CapabilityStatement",
+            "fhirVersion": "4.0.1",
+            "software": { "name": "Mock EHR", "version": "2.1" },
+            "rest": [{
+                "mode": "server",
+                "resource": [
+                    {
+                        "type": "Patient",
+                        "interaction": [{ "code": "read" }, { "code": "search-type" }],
+                        "searchParam": [{ "name": "identifier" }, { "name": "family" }]
+                    },
+                    { "type": "DocumentReference", "interaction": document_interactions }
+                ],
+                "operation": [{ "name": "export" }]
+            }]
+        })
+    }
+
+    async fn mount_metadata(server: &MockServer, document_push: bool) {
+        Mock::given(method("GET"))
+            .and(path("/fhir/metadata"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(statement(document_push)))
+            .mount(server)
+            .await;
+    }
+
+    fn token_response(token: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({ "access_token": token, "expires_in": 3600 }))
+    }
+
+    async fn mount_token(server: &MockServer, token: &str, expected_requests: u64) {
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("grant_type=client_credentials"))
+            .respond_with(token_response(token))
+            .expect(expected_requests)
+            .mount(server)
+            .await;
+    }
+
+    fn patient(id: &str) -> Value {
+        json!({ "resourceType": "Patient", "id": id })
+    }
+
+    fn bundle(resources: Vec<Value>, next: Option<String>) -> Value {
+        let mut bundle = json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "entry": resources.into_iter().map(|resource| json!({ "resource": resource })).collect::<Vec<_>>(),
+        });
+        if let Some(next) = next {
+            bundle["link"] = json!([{ "relation": "next", "url": next }]);
+        }
+        bundle
+    }
+
+    fn document(encounter_id: Option<&str>) -> DocumentPush {
+        DocumentPush {
+            patient_id: "erXuFYUfucBZaryVksYEcMg3".to_string(),
+            encounter_id: encounter_id.map(str::to_string),
+            type_code: "11506-3".to_string(),
+            type_display: "Progress note".to_string(),
+            title: "Follow-up visit".to_string(),
+            content_type: "text/plain".to_string(),
+            content: b"Afebrile, healing well.".to_vec(),
+            created: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_epic_adapter() {
+        let server = MockServer::start().await;
+        let auth = AdapterAuth::BackendServices {
+            token_url: format!("{}/token", server.uri()),
+            client_id: "epic-client".to_string(),
+            private_key_pem: BACKEND_SERVICES_KEY.to_string(),
+            key_id: Some("hims-2024".to_string()),
+            scope: None,
+        };
+        assert!(matches!(
+            adapter_for(config(Vendor::Epic, &server, client_credentials(&server))),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+        let epic = adapter_for(config(Vendor::Epic, &server, auth)).unwrap();
+        assert_eq!(epic.vendor(), Vendor::Epic);
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains(
+                "client_assertion_type=urn%3Aietf%3Aparams%3Aoauth%3Aclient-assertion-type%3Ajwt-bearer",
+            ))
+            .respond_with(token_response("epic-token"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_metadata(&server, true).await;
+        let location = format!("{}/fhir/DocumentReference/doc-9/_history/1", server.uri());
+        Mock::given(method("POST"))
+            .and(path("/fhir/DocumentReference"))
+            .and(bearer_token("epic-token"))
+            .and(header("content-type", FHIR_JSON))
+            .and(body_partial_json(json!({
+                "resourceType": "DocumentReference",
+                "subject": { "reference": "Patient/erXuFYUfucBZaryVksYEcMg3" },
+                "context": { "encounter": [{ "reference": "Encounter/eVisit-1" }] }
+            })))
+            .respond_with(ResponseTemplate::new(201).insert_header("location", location.as_str()))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        // Notes need an encounter, and searches an identifier or full demographics
+        assert!(matches!(epic.push_document(&document(None)).await, Err(HimsError::ValidationError { .. })));
+        let by_name = PatientQuery {
+            family: Some("Lin".to_string()),
+            ..PatientQuery::default()
+        };
+        assert!(matches!(epic.find_patients(&by_name).await, Err(HimsError::ValidationError { .. })));
+
+        // The token is fetched once and reused
+        for _ in 0..2 {
+            let pushed = epic.push_document(&document(Some("eVisit-1"))).await.unwrap();
+            assert_eq!(pushed.id.as_deref(), Some("doc-9"));
+        }
+
+        let requests = server.received_requests().await.unwrap();
+        let token_request = requests.iter().find(|request| request.url.path() == "/token").unwrap();
+        let form = String::from_utf8(token_request.body.clone()).unwrap();
+        let assertion = form
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("client_assertion="))
+            .unwrap();
+        let header = jsonwebtoken::decode_header(assertion).unwrap();
+        assert_eq!(header.alg, Algorithm::RS384);
+        assert_eq!(header.kid.as_deref(), Some("hims-2024"));
+    }
+
+    #[tokio::test]
+    async fn test_cerner_adapter() {
+        let server = MockServer::start().await;
+        let cerner = adapter_for(config(Vendor::Cerner, &server, client_credentials(&server))).unwrap();
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(basic_auth("hims", "s3cret"))
+            .and(body_string_contains("scope=system%2FPatient.read+system%2FDocumentReference.write"))
+            .respond_with(token_response("cerner-token"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_metadata(&server, true).await;
+        let next = format!("{}/fhir/Patient?page=2", server.uri());
+        let outcome = json!({ "resourceType": "OperationOutcome", "issue": [] });
+        let first_page = bundle(vec![patient("12724066"), outcome], Some(next));
+        Mock::given(method("GET"))
+            .and(path("/fhir/Patient"))
+            .and(query_param("identifier", "urn:oid:2.16.840.1.113883.6.1000|12724066"))
+            .and(bearer_token("cerner-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(first_page))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fhir/Patient"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bundle(vec![patient("12724067")], None)))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/fhir/DocumentReference"))
+            .and(body_partial_json(json!({ "context": { "period": {} } })))
+            .respond_with(
+                ResponseTemplate::new(201).set_body_json(json!({ "resourceType": "DocumentReference", "id": "197" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(cerner.authenticate().await.unwrap().as_deref(), Some("cerner-token"));
+
+        // Pages are followed by their next links, and only Patients kept
+        let query = PatientQuery {
+            identifier_system: Some("urn:oid:2.16.840.1.113883.6.1000".to_string()),
+            identifier: Some("12724066".to_string()),
+            ..PatientQuery::default()
+        };
+        let patients = cerner.find_patients(&query).await.unwrap();
+        let ids: Vec<&str> = patients.iter().filter_map(|patient| patient["id"].as_str()).collect();
+        assert_eq!(ids, vec!["12724066", "12724067"]);
+
+        // Millennium gets a context period even without an encounter
+        let pushed = cerner.push_document(&document(None)).await.unwrap();
+        assert_eq!(pushed.id.as_deref(), Some("197"));
+    }
+
+    #[tokio::test]
+    async fn test_athena_adapter() {
+        let server = MockServer::start().await;
+        let practice = "Organization/a-1.Practice-195900";
+        let auth = || AdapterAuth::Bearer {
+            token: "athena-token".to_string(),
+        };
+        assert!(matches!(
+            adapter_for(config(Vendor::Athena, &server, auth())),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+        let athena = adapter_for(AdapterConfig {
+            practice: Some(practice.to_string()),
+            ..config(Vendor::Athena, &server, auth())
+        })
+        .unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/fhir/Patient/a-1.E-14914"))
+            .and(query_param("ah-practice", practice))
+            .and(bearer_token("athena-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(patient("a-1.E-14914")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fhir/Patient/a-1.E-0"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        // A server that always has another page; searches stop after five
+        let next = format!("{}/fhir/Patient?page=next", server.uri());
+        Mock::given(method("GET"))
+            .and(path("/fhir/Patient"))
+            .and(query_param("ah-practice", practice))
+            .respond_with(ResponseTemplate::new(200).set_body_json(bundle(vec![patient("a-1.E-1")], Some(next))))
+            .expect(5)
+            .mount(&server)
+            .await;
+
+        assert_eq!(athena.read_patient("a-1.E-14914").await.unwrap().unwrap()["id"], "a-1.E-14914");
+        assert!(athena.read_patient("a-1.E-0").await.unwrap().is_none());
+
+        let query = PatientQuery {
+            family: Some("Okafor".to_string()),
+            birth_date: NaiveDate::from_ymd_opt(1975, 3, 14),
+            ..PatientQuery::default()
+        };
+        assert_eq!(athena.find_patients(&query).await.unwrap().len(), 5);
+        let requests = server.received_requests().await.unwrap();
+        let first_search = requests.iter().find(|request| request.url.path() == "/fhir/Patient").unwrap();
+        assert_eq!(
+            first_search.url.query(),
+            Some("family=Okafor&birthdate=1975-03-14&ah-practice=Organization%2Fa-1.Practice-195900")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fhir_server_adapter() {
+        let server = MockServer::start().await;
+        let fhir = adapter_for(config(Vendor::Fhir, &server, client_credentials(&server))).unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/fhir/metadata"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(statement(false)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/fhir/.well-known/smart-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_endpoint": format!("{}/token", server.uri()),
+                "token_endpoint_auth_methods_supported": ["client_secret_basic", "private_key_jwt"]
+            })))
+            .mount(&server)
+            .await;
+
+        // Discovered once, then served from the cache
+        let capabilities = fhir.capabilities().await.unwrap();
+        assert_eq!(fhir.capabilities().await.unwrap().capabilities, capabilities.capabilities);
+        assert_eq!(capabilities.software.as_deref(), Some("Mock EHR 2.1"));
+        assert_eq!(capabilities.token_endpoint, Some(format!("{}/token", server.uri())));
+        for capability in [
+            Capability::PatientRead,
+            Capability::PatientSearchByIdentifier,
+            Capability::DocumentSearch,
+            Capability::BulkExport,
+            Capability::SmartBackendServices,
+        ] {
+            assert!(capabilities.supports(capability), "{:?}", capability);
+        }
+        assert!(!capabilities.supports(Capability::DocumentPush));
+        assert!(capabilities.supports_interaction("Patient", "read"));
+
+        // What the server does not support fails before any request is sent
+        let error = fhir.push_document(&document(None)).await.unwrap_err();
+        assert!(matches!(error, HimsError::ValidationError { .. }), "{:?}", error);
+        assert!(server.received_requests().await.unwrap().iter().all(|request| request.url.path() != "/token"));
+    }
+
+    #[tokio::test]
+    async fn test_error_mapping() {
+        let server = MockServer::start().await;
+        let fhir = adapter_for(config(Vendor::Fhir, &server, client_credentials(&server))).unwrap();
+        mount_metadata(&server, true).await;
+        // A 401 drops the cached token, so the next request fetches another
+        mount_token(&server, "fhir-token", 2).await;
+
+        let respond = |status: u16, diagnostics: &str| {
+            ResponseTemplate::new(status).set_body_json(json!({
+                "resourceType": "OperationOutcome",
+                "issue": [{ "severity": "error", "code": "processing", "diagnostics": diagnostics }]
+            }))
+        };
+        for (id, status, diagnostics) in [
+            ("p401", 401, "Token expired"),
+            ("p403", 403, "Not in care team"),
+            ("p422", 422, "Unknown identifier system"),
+            ("p500", 500, "Database unavailable"),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(format!("/fhir/Patient/{}", id)))
+                .respond_with(respond(status, diagnostics))
+                .mount(&server)
+                .await;
+        }
+
+        let error = fhir.read_patient("p401").await.unwrap_err();
+        assert!(matches!(error, HimsError::AuthenticationError { .. }), "{:?}", error);
+        let error = fhir.read_patient("p403").await.unwrap_err();
+        assert!(matches!(error, HimsError::SecurityError { .. }), "{:?}", error);
+        let error = fhir.read_patient("p422").await.unwrap_err();
+        assert!(matches!(&error, HimsError::ValidationError { message } if message.contains("Unknown identifier")));
+        let error = fhir.read_patient("p500").await.unwrap_err();
+        assert!(matches!(error, HimsError::NetworkError { .. }), "{:?}", error);
+
+        // The token endpoint refusing the client is an authentication error
+        let refused = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({ "error": "invalid_client" })))
+            .mount(&refused)
+            .await;
+        let adapter = adapter_for(config(Vendor::Cerner, &refused, client_credentials(&refused))).unwrap();
+        let error = adapter.authenticate().await.unwrap_err();
+        assert!(matches!(&error, HimsError::AuthenticationError { message } if message.contains("invalid_client")));
+
+        assert!(matches!(
+            FhirApi::new(AdapterConfig {
+                base_url: "ftp://ehr.example.org".to_string(),
+                ..config(Vendor::Fhir, &server, AdapterAuth::None)
+            }),
+            Err(HimsError::ConfigurationError { .. })
+        ));
     }
-}
\ No newline at end of file
+}
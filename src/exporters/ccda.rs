@@ -0,0 +1,471 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::constants::PART2_REDISCLOSURE_NOTICE;
+use crate::models::{CodeableConcept, Condition, ConditionCategory, ConditionVerificationStatus, Gender, Patient, Referral};
+
+/// Problem Section (entries required), C-CDA R2.1
+pub const PROBLEM_SECTION_TEMPLATE: &str = "2.16.840.1.113883.10.20.22.2.5.1";
+/// Problem Concern Act
+pub const PROBLEM_CONCERN_ACT_TEMPLATE: &str = "2.16.840.1.113883.10.20.22.4.3";
+/// Problem Observation
+pub const PROBLEM_OBSERVATION_TEMPLATE: &str = "2.16.840.1.113883.10.20.22.4.4";
+/// Extension of the C-CDA R2.1 versions of the templates above
+pub const CCDA_TEMPLATE_VERSION: &str = "2015-08-01";
+/// US Realm Header and the Referral Note document that extends it
+pub const US_REALM_HEADER_TEMPLATE: &str = "2.16.840.1.113883.10.20.22.1.1";
+pub const REFERRAL_NOTE_TEMPLATE: &str = "2.16.840.1.113883.10.20.22.1.14";
+/// Sections a Referral Note has besides Problems, with the extensions of
+/// their current versions
+pub const REASON_FOR_REFERRAL_SECTION_TEMPLATE: (&str, &str) = ("1.3.6.1.4.1.19376.1.5.3.1.3.1", "2014-06-09");
+pub const ALLERGIES_SECTION_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.22.2.6", "2015-08-01");
+pub const MEDICATIONS_SECTION_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.22.2.1", "2014-06-09");
+pub const PLAN_OF_TREATMENT_SECTION_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.22.2.10", "2014-06-09");
+
+pub(crate) const LOINC_OID: &str = "2.16.840.1.113883.6.1";
+pub(crate) const SNOMED_CT_OID: &str = "2.16.840.1.113883.6.96";
+const ACT_CODE_OID: &str = "2.16.840.1.113883.5.6";
+const ADMINISTRATIVE_GENDER_OID: &str = "2.16.840.1.113883.5.1";
+pub(crate) const CONFIDENTIALITY_OID: &str = "2.16.840.1.113883.5.25";
+
+/// Escape text for XML content and attribute values
+pub(crate) fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// HL7 TS timestamp in UTC
+pub(crate) fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%d%H%M%S+0000").to_string()
+}
+
+/// Generates the C-CDA Problems section of a patient's conditions
+pub struct CcdaProblemSection;
+
+impl CcdaProblemSection {
+    /// The `<section>` for `conditions`: a narrative table and one Problem
+    /// Concern Act per condition. Conditions entered in error are left out;
+    /// refuted ones are kept as negated observations.
+    pub fn generate(conditions: &[Condition]) -> String {
+        Self::section(conditions, false)
+    }
+
+    /// The section for `conditions` that include 42 CFR Part 2 substance use
+    /// records: marked restricted, with the prohibition on redisclosure
+    pub fn generate_part2(conditions: &[Condition]) -> String {
+        Self::section(conditions, true)
+    }
+
+    fn section(conditions: &[Condition], part2: bool) -> String {
+        let conditions: Vec<&Condition> = conditions
+            .iter()
+            .filter(|condition| condition.verification_status != ConditionVerificationStatus::EnteredInError)
+            .collect();
+
+        let mut xml = String::new();
+        if conditions.is_empty() {
+            xml.push_str("<section nullFlavor=\"NI\">");
+        } else {
+            xml.push_str("<section>");
+        }
+        xml.push_str(&format!(
+            "<templateId root=\"{}\" extension=\"{}\"/>",
+            PROBLEM_SECTION_TEMPLATE, CCDA_TEMPLATE_VERSION
+        ));
+        xml.push_str(&format!(
+            "<code code=\"11450-4\" codeSystem=\"{}\" codeSystemName=\"LOINC\" displayName=\"Problem list\"/>",
+            LOINC_OID
+        ));
+        xml.push_str("<title>Problems</title>");
+        if conditions.is_empty() {
+            xml.push_str("<text>No information about problems</text></section>");
+            return xml;
+        }
+
+        xml.push_str("<text>");
+        if part2 {
+            xml.push_str(&format!("<paragraph>{}</paragraph>", escape_xml(PART2_REDISCLOSURE_NOTICE)));
+        }
+        xml.push_str("<table><thead><tr><th>Problem</th><th>Status</th><th>Onset</th><th>Resolved</th></tr></thead><tbody>");
+        for (index, condition) in conditions.iter().enumerate() {
+            xml.push_str(&format!(
+                "<tr ID=\"problem-{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                index + 1,
+                escape_xml(&Self::display(condition)),
+                condition.clinical_status.as_str(),
+                condition.onset.map(|at| at.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                condition.abatement.map(|at| at.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            ));
+        }
+        xml.push_str("</tbody></table></text>");
+        if part2 {
+            xml.push_str(&format!("<confidentialityCode code=\"R\" codeSystem=\"{}\"/>", CONFIDENTIALITY_OID));
+        }
+
+        for (index, condition) in conditions.iter().enumerate() {
+            xml.push_str(&Self::entry(condition, index + 1));
+        }
+        xml.push_str("</section>");
+        xml
+    }
+
+    /// Problem Concern Act wrapping the condition's Problem Observation
+    fn entry(condition: &Condition, row: usize) -> String {
+        // The concern stays active while the problem is a current concern
+        let concern_status = if condition.clinical_status.is_active() { "active" } else { "completed" };
+        let negation = if condition.verification_status == ConditionVerificationStatus::Refuted {
+            " negationInd=\"true\""
+        } else {
+            ""
+        };
+        // Problem (55607006) for problem list items, Diagnosis (282291009)
+        // for encounter diagnoses
+        let (type_code, type_display) = match condition.category {
+            ConditionCategory::ProblemListItem => ("55607006", "Problem"),
+            ConditionCategory::EncounterDiagnosis => ("282291009", "Diagnosis"),
+        };
+        let low = match condition.onset {
+            Some(onset) => format!("<low value=\"{}\"/>", timestamp(onset)),
+            None => "<low nullFlavor=\"UNK\"/>".to_string(),
+        };
+        let high = condition
+            .abatement
+            .map(|abatement| format!("<high value=\"{}\"/>", timestamp(abatement)))
+            .unwrap_or_default();
+        let value = match condition.snomed_code() {
+            Some(code) => format!(
+                "<value xsi:type=\"CD\" code=\"{}\" codeSystem=\"{}\" codeSystemName=\"SNOMED CT\" displayName=\"{}\"/>",
+                escape_xml(code),
+                SNOMED_CT_OID,
+                escape_xml(&Self::display(condition))
+            ),
+            None => "<value xsi:type=\"CD\" nullFlavor=\"UNK\"/>".to_string(),
+        };
+
+        format!(
+            "<entry typeCode=\"DRIV\"><act classCode=\"ACT\" moodCode=\"EVN\">\
+             <templateId root=\"{concern}\" extension=\"{version}\"/>\
+             <id root=\"{id}\"/>\
+             <code code=\"CONC\" codeSystem=\"{act_code}\" displayName=\"Concern\"/>\
+             <statusCode code=\"{concern_status}\"/>\
+             <effectiveTime><low value=\"{recorded}\"/></effectiveTime>\
+             <entryRelationship typeCode=\"SUBJ\"><observation classCode=\"OBS\" moodCode=\"EVN\"{negation}>\
+             <templateId root=\"{observation}\" extension=\"{version}\"/>\
+             <id root=\"{id}\" extension=\"observation\"/>\
+             <code code=\"{type_code}\" codeSystem=\"{snomed}\" codeSystemName=\"SNOMED CT\" displayName=\"{type_display}\"/>\
+             <text><reference value=\"#problem-{row}\"/></text>\
+             <statusCode code=\"completed\"/>\
+             <effectiveTime>{low}{high}</effectiveTime>\
+             {value}\
+             </observation></entryRelationship></act></entry>",
+            concern = PROBLEM_CONCERN_ACT_TEMPLATE,
+            observation = PROBLEM_OBSERVATION_TEMPLATE,
+            version = CCDA_TEMPLATE_VERSION,
+            id = condition.id,
+            act_code = ACT_CODE_OID,
+            recorded = timestamp(condition.recorded_date),
+            snomed = SNOMED_CT_OID,
+        )
+    }
+
+    /// Display of the condition's SNOMED CT coding, else its text
+    fn display(condition: &Condition) -> String {
+        condition
+            .code
+            .coding
+            .iter()
+            .find(|coding| coding.code.as_deref() == condition.snomed_code())
+            .and_then(|coding| coding.display.clone())
+            .or_else(|| condition.code.text.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Generates a C-CDA Referral Note: the referral's reason, the patient's
+/// problems, and the plan of sending them on
+pub struct CcdaReferralNote;
+
+impl CcdaReferralNote {
+    /// The `<ClinicalDocument>` for `referral` of `patient`, whose
+    /// `problems` go in the Problems section. Allergies and medications
+    /// are not kept here, so those sections say there is no information.
+    pub fn generate(referral: &Referral, patient: &Patient, problems: &[Condition], now: DateTime<Utc>) -> String {
+        Self::document(referral, patient, problems, now, false)
+    }
+
+    /// The note for `problems` that include 42 CFR Part 2 substance use
+    /// records: restricted, with the prohibition on redisclosure
+    pub fn generate_part2(referral: &Referral, patient: &Patient, problems: &[Condition], now: DateTime<Utc>) -> String {
+        Self::document(referral, patient, problems, now, true)
+    }
+
+    fn document(referral: &Referral, patient: &Patient, problems: &[Condition], now: DateTime<Utc>, part2: bool) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <ClinicalDocument xmlns=\"urn:hl7-org:v3\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\
+             <realmCode code=\"US\"/><typeId root=\"2.16.840.1.113883.1.3\" extension=\"POCD_HD000040\"/>",
+        );
+        for template in [US_REALM_HEADER_TEMPLATE, REFERRAL_NOTE_TEMPLATE] {
+            xml.push_str(&format!("<templateId root=\"{}\" extension=\"{}\"/>", template, CCDA_TEMPLATE_VERSION));
+        }
+        xml.push_str(&format!(
+            "<id root=\"{}\"/>\
+             <code code=\"57133-1\" codeSystem=\"{}\" codeSystemName=\"LOINC\" displayName=\"Referral note\"/>\
+             <title>Referral note</title>\
+             <effectiveTime value=\"{}\"/>\
+             <confidentialityCode code=\"{}\" codeSystem=\"{}\"/>\
+             <languageCode code=\"en-US\"/>",
+            referral.id,
+            LOINC_OID,
+            timestamp(now),
+            if part2 { "R" } else { "N" },
+            CONFIDENTIALITY_OID
+        ));
+        xml.push_str(&Self::record_target(patient));
+
+        let author_id = match referral.requester_id {
+            Some(requester_id) => format!("<id root=\"{}\"/>", requester_id),
+            None => "<id nullFlavor=\"UNK\"/>".to_string(),
+        };
+        let author_name = referral
+            .requester_display
+            .as_ref()
+            .map(|name| format!("<assignedPerson><name>{}</name></assignedPerson>", escape_xml(name)))
+            .unwrap_or_default();
+        xml.push_str(&format!(
+            "<author><time value=\"{}\"/><assignedAuthor>{}<addr nullFlavor=\"UNK\"/><telecom nullFlavor=\"UNK\"/>{}</assignedAuthor></author>",
+            timestamp(referral.authored_on),
+            author_id,
+            author_name
+        ));
+        xml.push_str(
+            "<custodian><assignedCustodian><representedCustodianOrganization><id nullFlavor=\"UNK\"/>\
+             <telecom nullFlavor=\"UNK\"/><addr nullFlavor=\"UNK\"/></representedCustodianOrganization></assignedCustodian></custodian>",
+        );
+        if let Some(recipient) = referral.recipient() {
+            xml.push_str(&format!(
+                "<informationRecipient><intendedRecipient><informationRecipient><name>{}</name></informationRecipient></intendedRecipient></informationRecipient>",
+                escape_xml(&recipient)
+            ));
+        }
+
+        xml.push_str("<component><structuredBody>");
+        for section in [
+            Self::reason_section(referral),
+            CcdaProblemSection::section(problems, part2),
+            Self::empty_section(ALLERGIES_SECTION_TEMPLATE, "48765-2", "Allergies and adverse reactions", "Allergies"),
+            Self::empty_section(MEDICATIONS_SECTION_TEMPLATE, "10160-0", "History of medication use", "Medications"),
+            Self::plan_section(referral),
+        ] {
+            xml.push_str(&format!("<component>{}</component>", section));
+        }
+        xml.push_str("</structuredBody></component></ClinicalDocument>");
+        xml
+    }
+
+    fn record_target(patient: &Patient) -> String {
+        let mut ids = format!("<id root=\"{}\"/>", patient.id);
+        for identifier in &patient.identifier {
+            if let Some(oid) = identifier.system.as_deref().and_then(|system| system.strip_prefix("urn:oid:")) {
+                ids.push_str(&format!("<id root=\"{}\" extension=\"{}\"/>", escape_xml(oid), escape_xml(&identifier.value)));
+            }
+        }
+        let name = match patient.name.first() {
+            Some(name) if name.family.is_some() || !name.given.is_empty() => format!(
+                "<name>{}{}</name>",
+                name.given.iter().map(|given| format!("<given>{}</given>", escape_xml(given))).collect::<String>(),
+                name.family.as_ref().map(|family| format!("<family>{}</family>", escape_xml(family))).unwrap_or_default()
+            ),
+            Some(name) => format!("<name>{}</name>", escape_xml(name.text.as_deref().unwrap_or_default())),
+            None => "<name nullFlavor=\"UNK\"/>".to_string(),
+        };
+        let gender = match patient.gender {
+            Gender::Male => format!("<administrativeGenderCode code=\"M\" codeSystem=\"{}\"/>", ADMINISTRATIVE_GENDER_OID),
+            Gender::Female => format!("<administrativeGenderCode code=\"F\" codeSystem=\"{}\"/>", ADMINISTRATIVE_GENDER_OID),
+            Gender::Other => format!("<administrativeGenderCode code=\"UN\" codeSystem=\"{}\"/>", ADMINISTRATIVE_GENDER_OID),
+            Gender::Unknown => "<administrativeGenderCode nullFlavor=\"UNK\"/>".to_string(),
+        };
+        let birth_time = match patient.birth_date {
+            Some(birth_date) => format!("<birthTime value=\"{}\"/>", birth_date.format("%Y%m%d")),
+            None => "<birthTime nullFlavor=\"UNK\"/>".to_string(),
+        };
+        format!(
+            "<recordTarget><patientRole>{}<addr nullFlavor=\"UNK\"/><telecom nullFlavor=\"UNK\"/>\
+             <patient>{}{}{}</patient></patientRole></recordTarget>",
+            ids, name, gender, birth_time
+        )
+    }
+
+    /// Why the patient is referred, to whom, and how soon
+    fn reason_section(referral: &Referral) -> String {
+        let mut items: Vec<String> = referral.reason_code.iter().filter_map(CodeableConcept::display_text).collect();
+        if items.is_empty() {
+            items.push("Not stated".to_string());
+        }
+        let mut text = format!(
+            "<list>{}</list>",
+            items.iter().map(|item| format!("<item>{}</item>", escape_xml(item))).collect::<String>()
+        );
+        if let Some(recipient) = referral.recipient() {
+            text.push_str(&format!("<paragraph>Referred to: {}</paragraph>", escape_xml(&recipient)));
+        }
+        text.push_str(&format!("<paragraph>Priority: {}</paragraph>", referral.priority.as_str()));
+        if let Some(note) = &referral.note {
+            text.push_str(&format!("<paragraph>{}</paragraph>", escape_xml(note)));
+        }
+        format!(
+            "<section><templateId root=\"{}\" extension=\"{}\"/>\
+             <code code=\"42349-1\" codeSystem=\"{}\" codeSystemName=\"LOINC\" displayName=\"Reason for referral\"/>\
+             <title>Reason for Referral</title><text>{}</text></section>",
+            REASON_FOR_REFERRAL_SECTION_TEMPLATE.0, REASON_FOR_REFERRAL_SECTION_TEMPLATE.1, LOINC_OID, text
+        )
+    }
+
+    /// What is asked for, and the appointment when one is booked
+    fn plan_section(referral: &Referral) -> String {
+        let mut text = match referral.code.as_ref().and_then(CodeableConcept::display_text) {
+            Some(requested) => format!("<paragraph>Requested: {}</paragraph>", escape_xml(&requested)),
+            None => "<paragraph>Assessment and management by the receiving clinician</paragraph>".to_string(),
+        };
+        if let Some(appointment_id) = referral.appointment_id {
+            text.push_str(&format!("<paragraph>Appointment booked: {}</paragraph>", appointment_id));
+        }
+        format!(
+            "<section><templateId root=\"{}\" extension=\"{}\"/>\
+             <code code=\"18776-5\" codeSystem=\"{}\" codeSystemName=\"LOINC\" displayName=\"Plan of care note\"/>\
+             <title>Plan of Treatment</title><text>{}</text></section>",
+            PLAN_OF_TREATMENT_SECTION_TEMPLATE.0, PLAN_OF_TREATMENT_SECTION_TEMPLATE.1, LOINC_OID, text
+        )
+    }
+
+    /// A required section with nothing recorded for it
+    fn empty_section(template: (&str, &str), code: &str, display: &str, title: &str) -> String {
+        format!(
+            "<section nullFlavor=\"NI\"><templateId root=\"{}\" extension=\"{}\"/>\
+             <code code=\"{}\" codeSystem=\"{}\" codeSystemName=\"LOINC\" displayName=\"{}\"/>\
+             <title>{}</title><text>No information about {}</text></section>",
+            template.0,
+            template.1,
+            code,
+            LOINC_OID,
+            display,
+            title,
+            title.to_lowercase()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::constants::SNOMED_CT_SYSTEM;
+    use crate::models::{CodeableConcept, Coding};
+    use uuid::Uuid;
+
+    fn condition(code: &str, display: &str) -> Condition {
+        let code = CodeableConcept {
+            coding: vec![Coding {
+                system: Some(SNOMED_CT_SYSTEM.to_string()),
+                version: None,
+                code: Some(code.to_string()),
+                display: Some(display.to_string()),
+            }],
+            text: None,
+        };
+        Condition::new(Uuid::new_v4(), code, ConditionCategory::ProblemListItem)
+    }
+
+    #[test]
+    fn test_problem_section() {
+        let diabetes = condition("73211009", "Diabetes mellitus");
+        let mut resolved = condition("59621000", "Essential hypertension & <other>");
+        resolved.resolve(Utc::now());
+        let mut error = condition("38341003", "Hypertensive disorder");
+        error.verification_status = ConditionVerificationStatus::EnteredInError;
+
+        let xml = CcdaProblemSection::generate(&[diabetes.clone(), resolved, error]);
+        let namespaced = xml.replace(
+            "<section>",
+            "<section xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">",
+        );
+        let document = roxmltree::Document::parse(&namespaced).unwrap();
+        let acts: Vec<_> = document.descendants().filter(|node| node.has_tag_name("act")).collect();
+        assert_eq!(acts.len(), 2);
+        let statuses: Vec<_> = acts
+            .iter()
+            .filter_map(|act| act.children().find(|node| node.has_tag_name("statusCode")))
+            .filter_map(|status| status.attribute("code"))
+            .collect();
+        assert_eq!(statuses, vec!["active", "completed"]);
+        assert!(xml.contains(&format!("<id root=\"{}\"/>", diabetes.id)));
+        assert!(xml.contains("code=\"73211009\" codeSystem=\"2.16.840.1.113883.6.96\""));
+        assert!(xml.contains("Essential hypertension &amp; &lt;other&gt;"));
+        assert!(!xml.contains("38341003"));
+
+        assert!(CcdaProblemSection::generate(&[]).starts_with("<section nullFlavor=\"NI\">"));
+    }
+
+    #[test]
+    fn test_referral_note() {
+        use crate::models::{HumanName, ReferralDirection};
+
+        let patient = Patient::new(
+            vec![HumanName {
+                use_type: None,
+                text: None,
+                family: Some("O'Brien".to_string()),
+                given: vec!["Ann".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            }],
+            vec![],
+            Gender::Female,
+            chrono::NaiveDate::from_ymd_opt(1980, 2, 29),
+        );
+        let mut referral = Referral::new(patient.id, ReferralDirection::Outbound);
+        referral.reason_code.push(CodeableConcept {
+            coding: vec![],
+            text: Some("Chest pain on exertion".to_string()),
+        });
+        referral.performer_display = Some("Cardiology <outpatients>".to_string());
+        referral.requester_display = Some("Dr Lee".to_string());
+
+        let xml = CcdaReferralNote::generate(&referral, &patient, &[condition("73211009", "Diabetes mellitus")], Utc::now());
+        let document = roxmltree::Document::parse(&xml).unwrap();
+        let root = document.root_element();
+        assert_eq!(root.tag_name().name(), "ClinicalDocument");
+        let templates: Vec<_> = root
+            .children()
+            .filter(|node| node.has_tag_name("templateId"))
+            .filter_map(|node| node.attribute("root"))
+            .collect();
+        assert_eq!(templates, vec![US_REALM_HEADER_TEMPLATE, REFERRAL_NOTE_TEMPLATE]);
+
+        let section_codes: Vec<_> = document
+            .descendants()
+            .filter(|node| node.has_tag_name("section"))
+            .filter_map(|section| section.children().find(|node| node.has_tag_name("code")))
+            .filter_map(|code| code.attribute("code"))
+            .collect();
+        assert_eq!(section_codes, vec!["42349-1", "11450-4", "48765-2", "10160-0", "18776-5"]);
+        assert!(xml.contains("<item>Chest pain on exertion</item>"));
+        assert!(xml.contains("<family>O&apos;Brien</family>"));
+        assert!(xml.contains("<birthTime value=\"19800229\"/>"));
+        assert!(xml.contains("<name>Cardiology &lt;outpatients&gt;</name>"));
+        assert!(!xml.contains(PART2_REDISCLOSURE_NOTICE));
+
+        // Part 2 records go out restricted, with the notice against redisclosure
+        let xml = CcdaReferralNote::generate_part2(&referral, &patient, &[condition("66590003", "Alcohol dependence")], Utc::now());
+        let document = roxmltree::Document::parse(&xml).unwrap();
+        let confidentiality: Vec<_> = document
+            .descendants()
+            .filter(|node| node.has_tag_name("confidentialityCode"))
+            .filter_map(|node| node.attribute("code"))
+            .collect();
+        assert_eq!(confidentiality, vec!["R", "R"]);
+        assert!(xml.contains(&format!("<paragraph>{}</paragraph>", PART2_REDISCLOSURE_NOTICE)));
+    }
+}
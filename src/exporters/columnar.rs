@@ -0,0 +1,440 @@
+// FHIR resources flattened into columns, written as Parquet datasets
+// partitioned by resource type and date
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "parquet")]
+use crate::core::HimsError;
+
+/// How a column's values are typed in the dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Number,
+    Boolean,
+    Date,
+    /// An instant, in UTC with microsecond precision
+    Timestamp,
+}
+
+/// A column of a flattened resource: the element at `path`, dot-separated
+/// element names and array indexes, e.g. `code.coding.0.code`
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnSpec {
+    pub name: &'static str,
+    pub path: &'static str,
+    pub column_type: ColumnType,
+}
+
+/// The columns a resource type is flattened into, and the elements that
+/// date it, the first present taken
+#[derive(Debug)]
+pub struct ResourceSpec {
+    pub resource_type: &'static str,
+    pub date_paths: &'static [&'static str],
+    pub columns: &'static [ColumnSpec],
+}
+
+const fn column(name: &'static str, path: &'static str, column_type: ColumnType) -> ColumnSpec {
+    ColumnSpec { name, path, column_type }
+}
+
+/// Patients, dated by when they were last updated. Names, identifiers,
+/// contact details and address lines are left out.
+pub const PATIENT_DATASET: ResourceSpec = ResourceSpec {
+    resource_type: "Patient",
+    date_paths: &["meta.last_updated"],
+    columns: &[
+        column("id", "id", ColumnType::Text),
+        column("version_id", "meta.version_id", ColumnType::Text),
+        column("last_updated", "meta.last_updated", ColumnType::Timestamp),
+        column("active", "active", ColumnType::Boolean),
+        column("gender", "gender", ColumnType::Text),
+        column("birth_date", "birthDate", ColumnType::Date),
+        column("city", "address.0.city", ColumnType::Text),
+        column("state", "address.0.state", ColumnType::Text),
+        column("postal_code", "address.0.postal_code", ColumnType::Text),
+        column("country", "address.0.country", ColumnType::Text),
+    ],
+};
+
+/// Conditions, dated by their onset, or when they were recorded
+pub const CONDITION_DATASET: ResourceSpec = ResourceSpec {
+    resource_type: "Condition",
+    date_paths: &["onsetDateTime", "recordedDate"],
+    columns: &[
+        column("id", "id", ColumnType::Text),
+        column("version_id", "meta.version_id", ColumnType::Text),
+        column("last_updated", "meta.last_updated", ColumnType::Timestamp),
+        column("patient", "subject.reference", ColumnType::Text),
+        column("encounter", "encounter.reference", ColumnType::Text),
+        column("clinical_status", "clinicalStatus.coding.0.code", ColumnType::Text),
+        column("verification_status", "verificationStatus.coding.0.code", ColumnType::Text),
+        column("category", "category.0.coding.0.code", ColumnType::Text),
+        column("code_system", "code.coding.0.system", ColumnType::Text),
+        column("code", "code.coding.0.code", ColumnType::Text),
+        column("code_display", "code.coding.0.display", ColumnType::Text),
+        column("onset", "onsetDateTime", ColumnType::Timestamp),
+        column("abatement", "abatementDateTime", ColumnType::Timestamp),
+        column("recorded", "recordedDate", ColumnType::Timestamp),
+    ],
+};
+
+/// Observations, dated by when they were made. Blood pressure's systolic
+/// and diastolic readings are its first two components.
+pub const OBSERVATION_DATASET: ResourceSpec = ResourceSpec {
+    resource_type: "Observation",
+    date_paths: &["effectiveDateTime"],
+    columns: &[
+        column("id", "id", ColumnType::Text),
+        column("version_id", "meta.version_id", ColumnType::Text),
+        column("last_updated", "meta.last_updated", ColumnType::Timestamp),
+        column("status", "status", ColumnType::Text),
+        column("category", "category.0.coding.0.code", ColumnType::Text),
+        column("patient", "subject.reference", ColumnType::Text),
+        column("encounter", "encounter.reference", ColumnType::Text),
+        column("code_system", "code.coding.0.system", ColumnType::Text),
+        column("code", "code.coding.0.code", ColumnType::Text),
+        column("code_display", "code.coding.0.display", ColumnType::Text),
+        column("effective", "effectiveDateTime", ColumnType::Timestamp),
+        column("issued", "issued", ColumnType::Timestamp),
+        column("value", "valueQuantity.value", ColumnType::Number),
+        column("unit", "valueQuantity.code", ColumnType::Text),
+        column("component_1_code", "component.0.code.coding.0.code", ColumnType::Text),
+        column("component_1_value", "component.0.valueQuantity.value", ColumnType::Number),
+        column("component_1_unit", "component.0.valueQuantity.code", ColumnType::Text),
+        column("component_2_code", "component.1.code.coding.0.code", ColumnType::Text),
+        column("component_2_value", "component.1.valueQuantity.value", ColumnType::Number),
+        column("component_2_unit", "component.1.valueQuantity.code", ColumnType::Text),
+    ],
+};
+
+/// Resource types a dataset can hold
+pub const DATASET_RESOURCES: &[&ResourceSpec] = &[&PATIENT_DATASET, &CONDITION_DATASET, &OBSERVATION_DATASET];
+
+impl ResourceSpec {
+    /// The spec of a resource type, if datasets can hold it
+    pub fn find(resource_type: &str) -> Option<&'static ResourceSpec> {
+        DATASET_RESOURCES.iter().copied().find(|spec| spec.resource_type == resource_type)
+    }
+
+    /// The day a resource falls on, in UTC
+    pub fn date_of(&self, resource: &Value) -> Option<NaiveDate> {
+        self.date_paths.iter().find_map(|path| match flat_value(resolve(resource, path), ColumnType::Date) {
+            FlatValue::Date(date) => Some(date),
+            _ => None,
+        })
+    }
+}
+
+/// A value of a flattened resource
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlatValue {
+    Null,
+    Text(String),
+    Number(f64),
+    Boolean(bool),
+    Date(NaiveDate),
+    Timestamp(DateTime<Utc>),
+}
+
+/// The element at a path of a resource
+fn resolve<'a>(resource: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(resource, |value, segment| match value {
+        Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        Value::Object(fields) => fields.get(segment),
+        _ => None,
+    })
+}
+
+/// An element as a value of a column's type; null if it is missing or not
+/// of that type. Dates are read from dates and from instants, as their day
+/// in UTC.
+fn flat_value(value: Option<&Value>, column_type: ColumnType) -> FlatValue {
+    let instant = |text: &str| DateTime::parse_from_rfc3339(text).ok().map(|at| at.with_timezone(&Utc));
+    match (value, column_type) {
+        (Some(Value::String(text)), ColumnType::Text) => FlatValue::Text(text.clone()),
+        (Some(Value::Number(number)), ColumnType::Text) => FlatValue::Text(number.to_string()),
+        (Some(Value::Bool(flag)), ColumnType::Text) => FlatValue::Text(flag.to_string()),
+        (Some(Value::Number(number)), ColumnType::Number) => {
+            number.as_f64().map(FlatValue::Number).unwrap_or(FlatValue::Null)
+        }
+        (Some(Value::Bool(flag)), ColumnType::Boolean) => FlatValue::Boolean(*flag),
+        (Some(Value::String(text)), ColumnType::Date) => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .or_else(|| instant(text).map(|at| at.date_naive()))
+            .map(FlatValue::Date)
+            .unwrap_or(FlatValue::Null),
+        (Some(Value::String(text)), ColumnType::Timestamp) => {
+            instant(text).map(FlatValue::Timestamp).unwrap_or(FlatValue::Null)
+        }
+        _ => FlatValue::Null,
+    }
+}
+
+/// Flattened resources of one type that fall on one day, or on none
+#[derive(Debug, Clone)]
+pub struct DatasetPartition {
+    pub spec: &'static ResourceSpec,
+    pub date: Option<NaiveDate>,
+    /// A value per column of the spec, for each resource
+    pub rows: Vec<Vec<FlatValue>>,
+}
+
+impl DatasetPartition {
+    /// Where the partition's file goes in the dataset, Hive-style, so
+    /// Spark, pandas and DuckDB read the type and date from the path
+    pub fn path(&self) -> String {
+        let date = self
+            .date
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "__HIVE_DEFAULT_PARTITION__".to_string());
+        format!("resource_type={}/date={}/part-0.parquet", self.spec.resource_type, date)
+    }
+}
+
+/// Flatten resources of a type, partitioned by the day each falls on.
+/// Resources outside `from` to `to` are left out, and with a period given
+/// so are those without a date.
+pub fn partition_resources(
+    spec: &'static ResourceSpec,
+    resources: &[Value],
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Vec<DatasetPartition> {
+    let mut partitions: BTreeMap<Option<NaiveDate>, Vec<Vec<FlatValue>>> = BTreeMap::new();
+    for resource in resources {
+        let date = spec.date_of(resource);
+        let in_period = match date {
+            Some(date) => from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to),
+            None => from.is_none() && to.is_none(),
+        };
+        if !in_period {
+            continue;
+        }
+        let row = spec
+            .columns
+            .iter()
+            .map(|column| flat_value(resolve(resource, column.path), column.column_type))
+            .collect();
+        partitions.entry(date).or_default().push(row);
+    }
+    partitions
+        .into_iter()
+        .map(|(date, rows)| DatasetPartition { spec, date, rows })
+        .collect()
+}
+
+#[cfg(feature = "parquet")]
+mod arrow_batches {
+    use std::sync::Arc;
+
+    use arrow::array::{
+        ArrayRef, BooleanBuilder, Date32Builder, Float64Builder, StringBuilder, TimestampMicrosecondBuilder,
+    };
+    use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use arrow::record_batch::RecordBatch;
+    use chrono::NaiveDate;
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+
+    use super::{ColumnType, DatasetPartition, FlatValue};
+    use crate::core::HimsError;
+
+    fn data_type(column_type: ColumnType) -> DataType {
+        match column_type {
+            ColumnType::Text => DataType::Utf8,
+            ColumnType::Number => DataType::Float64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Date => DataType::Date32,
+            ColumnType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        }
+    }
+
+    fn internal(context: &str, e: impl std::fmt::Display) -> HimsError {
+        HimsError::InternalError { message: format!("{}: {}", context, e) }
+    }
+
+    impl DatasetPartition {
+        /// The partition as an Arrow record batch, a nullable column per
+        /// column of its spec
+        pub fn to_record_batch(&self) -> Result<RecordBatch, HimsError> {
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+            let fields: Vec<Field> = self
+                .spec
+                .columns
+                .iter()
+                .map(|column| Field::new(column.name, data_type(column.column_type), true))
+                .collect();
+            let arrays: Vec<ArrayRef> = self
+                .spec
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(index, column)| -> ArrayRef {
+                    let values = self.rows.iter().map(|row| &row[index]);
+                    match column.column_type {
+                        ColumnType::Text => {
+                            let mut builder = StringBuilder::new();
+                            for value in values {
+                                match value {
+                                    FlatValue::Text(text) => builder.append_value(text),
+                                    _ => builder.append_null(),
+                                }
+                            }
+                            Arc::new(builder.finish())
+                        }
+                        ColumnType::Number => {
+                            let mut builder = Float64Builder::new();
+                            for value in values {
+                                match value {
+                                    FlatValue::Number(number) => builder.append_value(*number),
+                                    _ => builder.append_null(),
+                                }
+                            }
+                            Arc::new(builder.finish())
+                        }
+                        ColumnType::Boolean => {
+                            let mut builder = BooleanBuilder::new();
+                            for value in values {
+                                match value {
+                                    FlatValue::Boolean(flag) => builder.append_value(*flag),
+                                    _ => builder.append_null(),
+                                }
+                            }
+                            Arc::new(builder.finish())
+                        }
+                        ColumnType::Date => {
+                            let mut builder = Date32Builder::new();
+                            for value in values {
+                                match value {
+                                    FlatValue::Date(date) => builder.append_value((*date - epoch).num_days() as i32),
+                                    _ => builder.append_null(),
+                                }
+                            }
+                            Arc::new(builder.finish())
+                        }
+                        ColumnType::Timestamp => {
+                            let mut builder = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+                            for value in values {
+                                match value {
+                                    FlatValue::Timestamp(at) => builder.append_value(at.timestamp_micros()),
+                                    _ => builder.append_null(),
+                                }
+                            }
+                            Arc::new(builder.finish())
+                        }
+                    }
+                })
+                .collect();
+            RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+                .map_err(|e| internal("Failed to build record batch", e))
+        }
+
+        /// The partition as a Parquet file, Snappy-compressed
+        pub fn to_parquet(&self) -> Result<Vec<u8>, HimsError> {
+            let batch = self.to_record_batch()?;
+            let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+            let mut writer = ArrowWriter::try_new(Vec::new(), batch.schema(), Some(properties))
+                .map_err(|e| internal("Failed to start Parquet file", e))?;
+            writer.write(&batch).map_err(|e| internal("Failed to write Parquet file", e))?;
+            writer.into_inner().map_err(|e| internal("Failed to finish Parquet file", e))
+        }
+    }
+}
+
+/// A dataset of partitions as a ZIP archive of Parquet files at their
+/// partitions' paths
+#[cfg(feature = "parquet")]
+pub fn parquet_dataset(partitions: &[DatasetPartition]) -> Result<Vec<u8>, HimsError> {
+    let files = partitions
+        .iter()
+        .map(|partition| Ok((partition.path(), partition.to_parquet()?)))
+        .collect::<Result<Vec<(String, Vec<u8>)>, HimsError>>()?;
+    let parts: Vec<(&str, &[u8])> = files.iter().map(|(name, data)| (name.as_str(), data.as_slice())).collect();
+    Ok(crate::exporters::tabular::zip_archive(&parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn observation(id: &str, effective: &str, value: Value) -> Value {
+        json!({
+            "resourceType": "Observation",
+            "id": id,
+            "meta": { "version_id": "1", "last_updated": "2024-03-05T08:00:00Z" },
+            "status": "final",
+            "subject": { "reference": "Patient/p1" },
+            "code": { "coding": [{ "system": "http://loinc.org", "code": "8867-4", "display": "Heart rate" }] },
+            "effectiveDateTime": effective,
+            "valueQuantity": { "value": value, "code": "/min" }
+        })
+    }
+
+    #[test]
+    fn test_flatten() {
+        let resource = observation("o1", "2024-03-04T23:30:00-02:00", json!(72));
+        assert_eq!(resolve(&resource, "code.coding.0.code"), Some(&json!("8867-4")));
+        assert_eq!(resolve(&resource, "code.coding.1.code"), None);
+        assert_eq!(resolve(&resource, "status.code"), None);
+        assert_eq!(flat_value(resolve(&resource, "valueQuantity.value"), ColumnType::Number), FlatValue::Number(72.0));
+        assert_eq!(flat_value(resolve(&resource, "status"), ColumnType::Number), FlatValue::Null);
+        // 23:30 at UTC-2 is the next day in UTC
+        assert_eq!(OBSERVATION_DATASET.date_of(&resource), NaiveDate::from_ymd_opt(2024, 3, 5));
+        assert_eq!(
+            flat_value(Some(&json!("1970-05-17")), ColumnType::Date),
+            FlatValue::Date(NaiveDate::from_ymd_opt(1970, 5, 17).unwrap())
+        );
+
+        let condition = json!({ "resourceType": "Condition", "recordedDate": "2024-02-01T10:00:00Z" });
+        assert_eq!(CONDITION_DATASET.date_of(&condition), NaiveDate::from_ymd_opt(2024, 2, 1));
+        assert!(ResourceSpec::find("Observation").is_some());
+        assert!(ResourceSpec::find("Claim").is_none());
+    }
+
+    #[test]
+    fn test_partitions() {
+        let resources = vec![
+            observation("o1", "2024-03-01T09:00:00Z", json!(72)),
+            observation("o2", "2024-03-02T09:00:00Z", json!(80)),
+            observation("o3", "2024-03-01T17:00:00Z", json!("n/a")),
+            observation("o4", "not a date", json!(60)),
+        ];
+        let partitions = partition_resources(&OBSERVATION_DATASET, &resources, None, None);
+        let paths: Vec<String> = partitions.iter().map(|partition| partition.path()).collect();
+        assert_eq!(
+            paths,
+            [
+                "resource_type=Observation/date=__HIVE_DEFAULT_PARTITION__/part-0.parquet",
+                "resource_type=Observation/date=2024-03-01/part-0.parquet",
+                "resource_type=Observation/date=2024-03-02/part-0.parquet",
+            ]
+        );
+        assert_eq!(partitions[1].rows.len(), 2);
+        assert_eq!(partitions[1].rows[1][0], FlatValue::Text("o3".to_string()));
+        assert_eq!(partitions[1].rows[1][12], FlatValue::Null);
+
+        let day = NaiveDate::from_ymd_opt(2024, 3, 2);
+        let partitions = partition_resources(&OBSERVATION_DATASET, &resources, day, day);
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].rows.len(), 1);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet() {
+        let resources = vec![observation("o1", "2024-03-01T09:00:00Z", json!(72))];
+        let partitions = partition_resources(&OBSERVATION_DATASET, &resources, None, None);
+        let batch = partitions[0].to_record_batch().unwrap();
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.num_columns(), OBSERVATION_DATASET.columns.len());
+        let file = partitions[0].to_parquet().unwrap();
+        assert!(file.starts_with(b"PAR1") && file.ends_with(b"PAR1"));
+        assert!(parquet_dataset(&partitions).unwrap().starts_with(b"PK\x03\x04"));
+    }
+}
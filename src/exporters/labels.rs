@@ -0,0 +1,567 @@
+// Printable labels for patient wristbands and specimen tubes: a Code 128
+// or QR code beside the patient's details, as ZPL for Zebra thermal
+// printers or as PDF for any other
+//
+// Identifiers are coded with an ISO 7064 MOD 37-2 check character, as on
+// ISBT 128 labels, so one keyed in wrongly or misread at the bedside is
+// caught rather than matched to the wrong patient or specimen.
+
+use chrono::{DateTime, Utc};
+use qrcode::{Color, EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+
+use crate::core::HimsError;
+use crate::exporters::pdf::pdf::{assemble, text_string};
+use crate::models::{Gender, Patient};
+
+/// Resolution ZPL is written for, in dots per inch
+const ZPL_DPI: f32 = 203.0;
+/// Quiet zone either side of a Code 128 symbol, and around a QR code, in modules
+const CODE128_QUIET_ZONE: usize = 10;
+const QR_QUIET_ZONE: usize = 4;
+
+/// Bar and space widths of Code 128 symbol values 0 to 105, in modules
+const CODE128_PATTERNS: [&str; 106] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212", "221213", "221312",
+    "231212", "112232", "122132", "122231", "113222", "123122", "123221", "223211", "221132", "221231", "213212",
+    "223112", "312131", "311222", "321122", "321221", "312212", "322112", "322211", "212123", "212321", "232121",
+    "111323", "131123", "131321", "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331",
+    "132131", "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131", "311123",
+    "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111", "111224", "111422", "121124",
+    "121421", "141122", "141221", "112214", "112412", "122114", "122411", "142112", "142211", "241211", "221114",
+    "413111", "241112", "134111", "111242", "121142", "121241", "114212", "124112", "124211", "411212", "421112",
+    "421211", "212141", "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232",
+];
+const CODE128_STOP: &str = "2331112";
+const CODE128_CODE_C: usize = 99;
+const CODE128_CODE_B: usize = 100;
+const CODE128_START_B: usize = 104;
+const CODE128_START_C: usize = 105;
+
+/// Characters by their ISO 7064 MOD 37-2 value; `*` is only ever a check character
+const MOD37_CHARACTERS: &[u8; 37] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ*";
+
+/// Label stock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LabelSize {
+    /// 11 × 1 inch adult wristband
+    AdultWristband,
+    /// 6 × 1 inch infant wristband
+    InfantWristband,
+    /// 2 × 1 inch specimen tube label
+    Specimen,
+}
+
+impl LabelSize {
+    /// Width and height in inches, as fed through the printer
+    pub fn inches(&self) -> (f32, f32) {
+        match self {
+            LabelSize::AdultWristband => (11.0, 1.0),
+            LabelSize::InfantWristband => (6.0, 1.0),
+            LabelSize::Specimen => (2.0, 1.0),
+        }
+    }
+
+    /// Where printing starts, clear of a wristband's clasp
+    fn left_margin(&self) -> f32 {
+        match self {
+            LabelSize::AdultWristband | LabelSize::InfantWristband => 1.5,
+            LabelSize::Specimen => 0.08,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    Code128,
+    Qr,
+}
+
+/// A label: lines of text, the first in bold, beside a code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Label {
+    pub size: LabelSize,
+    pub symbology: Symbology,
+    /// What the code encodes
+    pub data: String,
+    pub lines: Vec<String>,
+}
+
+/// Place on the label, in inches from its top left corner
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// The code to print: bar and space widths, or the dark modules of a QR
+/// code row by row, with the module size in printer dots
+#[derive(Debug)]
+enum Symbol {
+    Bars { widths: Vec<u8>, module: u32 },
+    Matrix { size: usize, dark: Vec<bool>, module: u32 },
+}
+
+impl Label {
+    /// A wristband coding a patient's `identifier`, such as their MRN, with
+    /// its check character
+    pub fn wristband(patient: &Patient, identifier: &str, size: LabelSize) -> Result<Self, HimsError> {
+        let coded = with_check_character(identifier)?;
+        Ok(Self {
+            size,
+            symbology: Symbology::Code128,
+            lines: vec![display_name(patient), demographics(patient), format!("MRN {}", coded)],
+            data: coded,
+        })
+    }
+
+    /// A specimen tube label coding the specimen's accession number with
+    /// its check character
+    pub fn specimen(
+        patient: &Patient,
+        identifier: &str,
+        accession: &str,
+        specimen_type: &str,
+        collected_at: DateTime<Utc>,
+    ) -> Result<Self, HimsError> {
+        let coded = with_check_character(accession)?;
+        Ok(Self {
+            size: LabelSize::Specimen,
+            symbology: Symbology::Code128,
+            lines: vec![
+                display_name(patient),
+                format!("{}  MRN {}", demographics(patient), identifier),
+                format!("{} {}", specimen_type, collected_at.format("%Y-%m-%d %H:%M")),
+                format!("ACC {}", coded),
+            ],
+            data: coded,
+        })
+    }
+
+    pub fn with_symbology(mut self, symbology: Symbology) -> Self {
+        self.symbology = symbology;
+        self
+    }
+
+    /// The label as ZPL II for a 203 dpi printer, which draws the code itself
+    pub fn to_zpl(&self) -> Result<String, HimsError> {
+        let (text, code, font) = self.layout();
+        let dots = |inches: f32| (inches * ZPL_DPI).round() as u32;
+        let (width, height) = self.size.inches();
+        let font_dots = dots(font / 72.0);
+
+        let mut zpl = format!("^XA\n^CI28\n^PW{}\n^LL{}\n", dots(width), dots(height));
+        for (i, line) in self.text_lines(text, font).iter().enumerate() {
+            let y = text.y + (i as f32 * font * 1.2) / 72.0;
+            zpl.push_str(&format!(
+                "^FO{},{}^A0N,{},{}^FH^FD{}^FS\n",
+                dots(text.x),
+                dots(y),
+                font_dots,
+                font_dots,
+                zpl_field(line)
+            ));
+        }
+        match self.symbol(code)? {
+            Symbol::Bars { module, .. } => zpl.push_str(&format!(
+                "^FO{},{}^BY{}^BCN,{},N,N,N,A^FH^FD{}^FS\n",
+                dots(code.x) + CODE128_QUIET_ZONE as u32 * module,
+                dots(code.y),
+                module,
+                dots(code.height),
+                zpl_field(&self.data)
+            )),
+            Symbol::Matrix { module, .. } => zpl.push_str(&format!(
+                "^FO{},{}^BQN,2,{}^FH^FDMA,{}^FS\n",
+                dots(code.x),
+                dots(code.y),
+                module,
+                zpl_field(&self.data)
+            )),
+        }
+        zpl.push_str("^XZ\n");
+        Ok(zpl)
+    }
+
+    /// Content stream of the label as a PDF page the size of its stock
+    fn pdf_page(&self) -> Result<(f32, f32, Vec<u8>), HimsError> {
+        let (text, code, font) = self.layout();
+        let (width, height) = self.size.inches();
+        // Points from the bottom left, from inches from the top left
+        let x = |inches: f32| inches * 72.0;
+        let y = |inches: f32| (height - inches) * 72.0;
+
+        let mut content = b"BT\n".to_vec();
+        for (i, line) in self.text_lines(text, font).iter().enumerate() {
+            let baseline = y(text.y) - font * (1.2 * i as f32 + 1.0);
+            let face = if i == 0 { "F2" } else { "F1" };
+            let position = format!("/{} {} Tf\n1 0 0 1 {:.2} {:.2} Tm\n(", face, font, x(text.x), baseline);
+            content.extend_from_slice(position.as_bytes());
+            content.extend_from_slice(&text_string(line));
+            content.extend_from_slice(b") Tj\n");
+        }
+        content.extend_from_slice(b"ET\n0 g\n");
+
+        let mut rect = |left: f32, top: f32, w: f32, h: f32| {
+            let corner = format!("{:.2} {:.2} {:.2} {:.2} re\n", x(left), y(top + h), x(w), x(h));
+            content.extend_from_slice(corner.as_bytes());
+        };
+        match self.symbol(code)? {
+            Symbol::Bars { widths, module } => {
+                let module = module as f32 / ZPL_DPI;
+                let mut left = code.x + CODE128_QUIET_ZONE as f32 * module;
+                for (i, width) in widths.iter().enumerate() {
+                    let width = *width as f32 * module;
+                    if i % 2 == 0 {
+                        rect(left, code.y, width, code.height);
+                    }
+                    left += width;
+                }
+            }
+            Symbol::Matrix { size, dark, module } => {
+                let module = module as f32 / ZPL_DPI;
+                for (i, _) in dark.iter().enumerate().filter(|(_, dark)| **dark) {
+                    let (row, column) = (i / size, i % size);
+                    rect(
+                        code.x + (QR_QUIET_ZONE + column) as f32 * module,
+                        code.y + (QR_QUIET_ZONE + row) as f32 * module,
+                        module,
+                        module,
+                    );
+                }
+            }
+        }
+        content.extend_from_slice(b"f");
+        Ok((x(width), x(height), content))
+    }
+
+    /// Where the text and code go, and the text's size in points. Wristbands
+    /// read along their length, specimen labels top to bottom.
+    fn layout(&self) -> (Area, Area, f32) {
+        let (width, height) = self.size.inches();
+        let left = self.size.left_margin();
+        match self.size {
+            LabelSize::AdultWristband | LabelSize::InfantWristband => {
+                let text_width = ((width - left - 0.2) / 2.0).min(2.6);
+                let text = Area { x: left, y: 0.1, width: text_width, height: height - 0.2 };
+                let code_x = left + text_width + 0.1;
+                let code = Area { x: code_x, y: 0.1, width: (width - code_x - 0.1).min(2.5), height: height - 0.2 };
+                (text, code, 8.0)
+            }
+            LabelSize::Specimen => {
+                let text = Area { x: left, y: 0.06, width: width - 2.0 * left, height: 0.42 };
+                let code = Area { x: left, y: 0.5, width: width - 2.0 * left, height: height - 0.56 };
+                (text, code, 6.0)
+            }
+        }
+    }
+
+    /// The lines that fit the text area, each cut to its width on an
+    /// average Helvetica character
+    fn text_lines(&self, area: Area, font: f32) -> Vec<String> {
+        let rows = (area.height * 72.0 / (font * 1.2)) as usize;
+        let characters = (area.width * 72.0 / (font * 0.55)) as usize;
+        self.lines
+            .iter()
+            .take(rows)
+            .map(|line| match line.chars().count() > characters {
+                true => line.chars().take(characters.saturating_sub(3)).chain("...".chars()).collect(),
+                false => line.clone(),
+            })
+            .collect()
+    }
+
+    /// The code at the largest whole number of dots per module that fits
+    /// its area
+    fn symbol(&self, area: Area) -> Result<Symbol, HimsError> {
+        let too_long = || HimsError::ValidationError {
+            message: format!("{:?} too long to print legibly on a {:?} label", self.data, self.size),
+        };
+        match self.symbology {
+            Symbology::Code128 => {
+                let widths = code128_widths(&self.data)?;
+                let modules = widths.iter().map(|w| *w as usize).sum::<usize>() + 2 * CODE128_QUIET_ZONE;
+                let module = (area.width * ZPL_DPI / modules as f32) as u32;
+                if module == 0 {
+                    return Err(too_long());
+                }
+                Ok(Symbol::Bars { widths, module })
+            }
+            Symbology::Qr => {
+                let code = QrCode::with_error_correction_level(self.data.as_bytes(), EcLevel::M).map_err(|e| {
+                    HimsError::ValidationError {
+                        message: format!("Cannot encode {:?} as a QR code: {}", self.data, e),
+                    }
+                })?;
+                let size = code.width();
+                let side = area.width.min(area.height);
+                // ZPL magnifies QR codes at most tenfold
+                let module = ((side * ZPL_DPI / (size + 2 * QR_QUIET_ZONE) as f32) as u32).min(10);
+                if module == 0 {
+                    return Err(too_long());
+                }
+                let dark = code.to_colors().into_iter().map(|color| color == Color::Dark).collect();
+                Ok(Symbol::Matrix { size, dark, module })
+            }
+        }
+    }
+}
+
+/// Labels as ZPL II, one after another
+pub fn labels_zpl(labels: &[Label]) -> Result<String, HimsError> {
+    labels.iter().map(Label::to_zpl).collect()
+}
+
+/// Labels as a PDF with a page per label, each the size of its stock
+pub fn labels_pdf(title: &str, labels: &[Label]) -> Result<Vec<u8>, HimsError> {
+    let pages = labels.iter().map(Label::pdf_page).collect::<Result<Vec<_>, _>>()?;
+    Ok(assemble(title, &pages))
+}
+
+/// The ISO 7064 MOD 37-2 check character of an identifier's letters and
+/// digits, ignoring case and separators such as dashes
+pub fn check_character(identifier: &str) -> Result<char, HimsError> {
+    let invalid = || HimsError::ValidationError {
+        message: format!("{:?} is not an identifier of letters and digits", identifier),
+    };
+    if !identifier.is_ascii() {
+        return Err(invalid());
+    }
+    let values: Vec<usize> = identifier.bytes().filter_map(mod37_value).collect();
+    if values.is_empty() {
+        return Err(invalid());
+    }
+    let sum = values.iter().fold(0, |sum, value| (sum + value) * 2 % 37);
+    Ok(MOD37_CHARACTERS[(38 - sum) % 37] as char)
+}
+
+/// An identifier, in capitals, followed by its check character
+pub fn with_check_character(identifier: &str) -> Result<String, HimsError> {
+    let check = check_character(identifier)?;
+    Ok(format!("{}{}", identifier.trim().to_ascii_uppercase(), check))
+}
+
+/// The identifier a scanned or keyed-in code carries, once its final check
+/// character is confirmed
+pub fn verify_check_character(coded: &str) -> Result<String, HimsError> {
+    let coded = coded.trim();
+    let mismatch = || HimsError::ValidationError {
+        message: format!("Check character of {:?} does not match; scan or enter it again", coded),
+    };
+    let check = coded.chars().last().ok_or_else(mismatch)?;
+    let identifier = &coded[..coded.len() - check.len_utf8()];
+    match check_character(identifier) {
+        Ok(expected) if expected == check.to_ascii_uppercase() => Ok(identifier.to_ascii_uppercase()),
+        _ => Err(mismatch()),
+    }
+}
+
+fn mod37_value(byte: u8) -> Option<usize> {
+    let byte = byte.to_ascii_uppercase();
+    MOD37_CHARACTERS[..36].iter().position(|c| *c == byte)
+}
+
+/// Code 128 symbol values of `data` from the start character to the check
+/// symbol, in code set C for runs of digits and B otherwise
+fn code128_values(data: &str) -> Result<Vec<usize>, HimsError> {
+    if data.is_empty() || !data.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return Err(HimsError::ValidationError {
+            message: format!("Code 128 labels take printable ASCII only, not {:?}", data),
+        });
+    }
+    let bytes = data.as_bytes();
+    let digits_at = |i: usize| bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let pair = |i: usize| ((bytes[i] - b'0') * 10 + (bytes[i + 1] - b'0')) as usize;
+
+    let leading = digits_at(0);
+    let mut set_c = leading >= 4 || (leading == bytes.len() && leading % 2 == 0);
+    let mut values = vec![if set_c { CODE128_START_C } else { CODE128_START_B }];
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = digits_at(i);
+        if set_c {
+            if run >= 2 {
+                values.push(pair(i));
+                i += 2;
+                continue;
+            }
+            values.push(CODE128_CODE_B);
+            set_c = false;
+        }
+        // Switching pays for itself on four digits at the end, six elsewhere
+        if run >= 4 && (i + run == bytes.len() || run >= 6) {
+            if run % 2 == 1 {
+                values.push((bytes[i] - b' ') as usize);
+                i += 1;
+            }
+            values.push(CODE128_CODE_C);
+            set_c = true;
+            continue;
+        }
+        values.push((bytes[i] - b' ') as usize);
+        i += 1;
+    }
+
+    let check = values.iter().enumerate().map(|(position, value)| position.max(1) * value).sum::<usize>() % 103;
+    values.push(check);
+    Ok(values)
+}
+
+/// Alternating bar and space widths of `data` as Code 128, stop character included
+fn code128_widths(data: &str) -> Result<Vec<u8>, HimsError> {
+    let values = code128_values(data)?;
+    Ok(values
+        .iter()
+        .flat_map(|value| CODE128_PATTERNS[*value].bytes())
+        .chain(CODE128_STOP.bytes())
+        .map(|width| width - b'0')
+        .collect())
+}
+
+/// Text for a ZPL field under `^FH`, with the characters ZPL treats as
+/// commands written as hex
+fn zpl_field(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '_' => "_5F".to_string(),
+            '^' => "_5E".to_string(),
+            '~' => "_7E".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// `FAMILY, Given Middle`, as wristbands print names
+fn display_name(patient: &Patient) -> String {
+    let Some(name) = patient.name.first() else {
+        return "UNKNOWN".to_string();
+    };
+    match &name.family {
+        Some(family) if name.given.is_empty() => family.to_uppercase(),
+        Some(family) => format!("{}, {}", family.to_uppercase(), name.given.join(" ")),
+        None => name.text.clone().unwrap_or_else(|| name.given.join(" ")),
+    }
+}
+
+/// `DOB 1980-04-02  F`
+fn demographics(patient: &Patient) -> String {
+    let sex = match patient.gender {
+        Gender::Male => "M",
+        Gender::Female => "F",
+        Gender::Other => "O",
+        Gender::Unknown => "U",
+    };
+    match patient.birth_date {
+        Some(date) => format!("DOB {}  {}", date.format("%Y-%m-%d"), sex),
+        None => format!("DOB unknown  {}", sex),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HumanName;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn patient() -> Patient {
+        let name = HumanName {
+            use_type: None,
+            text: None,
+            family: Some("Rao".to_string()),
+            given: vec!["Asha".to_string(), "K".to_string()],
+            prefix: Vec::new(),
+            suffix: Vec::new(),
+        };
+        Patient::new(vec![name], Vec::new(), Gender::Female, NaiveDate::from_ymd_opt(1980, 4, 2))
+    }
+
+    #[test]
+    fn test_code128() {
+        for pattern in CODE128_PATTERNS {
+            let widths: Vec<u8> = pattern.bytes().map(|w| w - b'0').collect();
+            assert_eq!(widths.iter().map(|w| *w as u32).sum::<u32>(), 11);
+            // Bars always add up to an even number of modules
+            assert_eq!(widths.iter().step_by(2).map(|w| *w as u32).sum::<u32>() % 2, 0);
+        }
+
+        // Set B, with its check symbol
+        assert_eq!(code128_values("PJJ123C").unwrap(), vec![104, 48, 42, 42, 17, 18, 19, 35, 55]);
+        // Digits go in pairs in set C
+        assert_eq!(code128_values("123456").unwrap(), vec![105, 12, 34, 56, 44]);
+        // Switching to C for trailing digits, the odd one first in B
+        let values = code128_values("A12345").unwrap();
+        assert_eq!(&values[..5], &[104, 33, 17, CODE128_CODE_C, 23]);
+        assert_eq!(values[5], 45);
+
+        let widths = code128_widths("123456").unwrap();
+        assert_eq!(widths.iter().map(|w| *w as u32).sum::<u32>(), 5 * 11 + 13);
+        assert!(code128_values("").is_err());
+        assert!(code128_values("café").is_err());
+    }
+
+    #[test]
+    fn test_check_character() {
+        let coded = with_check_character("mrn-004417").unwrap();
+        assert!(coded.starts_with("MRN-004417"));
+        assert_eq!(verify_check_character(&coded).unwrap(), "MRN-004417");
+
+        // Every single substitution and adjacent transposition is caught
+        let identifier = "ACC2409174";
+        let check = check_character(identifier).unwrap();
+        let characters: Vec<char> = identifier.chars().collect();
+        for i in 0..characters.len() {
+            for replacement in MOD37_CHARACTERS[..36].iter().map(|c| *c as char) {
+                if replacement != characters[i] {
+                    let mut wrong = characters.clone();
+                    wrong[i] = replacement;
+                    assert_ne!(check_character(&wrong.iter().collect::<String>()).unwrap(), check);
+                }
+            }
+            if i + 1 < characters.len() && characters[i] != characters[i + 1] {
+                let mut swapped = characters.clone();
+                swapped.swap(i, i + 1);
+                assert_ne!(check_character(&swapped.iter().collect::<String>()).unwrap(), check);
+            }
+        }
+        assert!(check_character("--").is_err());
+        assert!(verify_check_character("").is_err());
+    }
+
+    #[test]
+    fn test_labels() {
+        let patient = patient();
+        let wristband = Label::wristband(&patient, "004417", LabelSize::AdultWristband).unwrap();
+        assert_eq!(wristband.lines[0], "RAO, Asha K");
+        assert_eq!(wristband.lines[1], "DOB 1980-04-02  F");
+
+        let zpl = wristband.to_zpl().unwrap();
+        assert!(zpl.starts_with("^XA\n^CI28\n^PW2233\n^LL203\n"));
+        assert!(zpl.contains(&format!("^BCN,162,N,N,N,A^FH^FD{}^FS", wristband.data)));
+        assert!(zpl.ends_with("^XZ\n"));
+
+        let collected = Utc.with_ymd_and_hms(2024, 9, 17, 8, 30, 0).unwrap();
+        let specimen = Label::specimen(&patient, "004417", "ACC2409174", "Serum", collected).unwrap();
+        assert_eq!(specimen.lines[2], "Serum 2024-09-17 08:30");
+        let qr = specimen.clone().with_symbology(Symbology::Qr).to_zpl().unwrap();
+        assert!(qr.contains(&format!("^FDMA,{}^FS", specimen.data)));
+
+        let pdf = labels_pdf("Labels", &[wristband, specimen]).unwrap();
+        let contains = |needle: &[u8]| pdf.windows(needle.len()).any(|window| window == needle);
+        assert!(contains(b"/MediaBox [0 0 792 72]"));
+        assert!(contains(b"/MediaBox [0 0 144 72]"));
+        assert!(contains(b"(RAO, Asha K) Tj"));
+
+        let long = Label {
+            size: LabelSize::Specimen,
+            symbology: Symbology::Code128,
+            data: "X".repeat(60),
+            lines: Vec::new(),
+        };
+        assert!(matches!(long.to_zpl(), Err(HimsError::ValidationError { .. })));
+    }
+}
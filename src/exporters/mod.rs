@@ -2,8 +2,22 @@ pub mod pdf;
 pub mod x12_edi;
 pub mod csv_fhir_import;
 pub mod api_adapters;
+pub mod pdmp;
+pub mod ccda;
+pub mod tabular;
+pub mod omop;
+pub mod columnar;
+pub mod abha;
+pub mod labels;
 
 pub use pdf::*;
 pub use x12_edi::*;
 pub use csv_fhir_import::*;
-pub use api_adapters::*;
\ No newline at end of file
+pub use api_adapters::*;
+pub use pdmp::*;
+pub use ccda::*;
+pub use tabular::*;
+pub use omop::*;
+pub use columnar::*;
+pub use abha::*;
+pub use labels::*;
@@ -0,0 +1,668 @@
+// Clinical records as OMOP Common Data Model (v5.4) tables, for research
+// data warehouses
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::exporters::tabular::{zip_archive, Cell, Table};
+use crate::models::constants::NDC_SYSTEM;
+use crate::models::CodeableConcept;
+use crate::standards::terminology::TerminologyService;
+
+/// Gender concepts of the OMOP Gender vocabulary
+pub const OMOP_MALE: i32 = 8507;
+pub const OMOP_FEMALE: i32 = 8532;
+
+/// Visit concepts: inpatient, outpatient, emergency room and home visits
+pub const OMOP_INPATIENT_VISIT: i32 = 9201;
+pub const OMOP_OUTPATIENT_VISIT: i32 = 9202;
+pub const OMOP_EMERGENCY_VISIT: i32 = 9203;
+pub const OMOP_HOME_VISIT: i32 = 581476;
+
+/// Type concept of every record: it came from an EHR
+pub const OMOP_EHR_TYPE: i32 = 32817;
+
+/// A code as recorded, in the OMOP vocabulary its FHIR system is
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceCode {
+    pub vocabulary_id: String,
+    pub concept_code: String,
+}
+
+impl SourceCode {
+    /// The codings of a concept that are in an OMOP vocabulary, NDCs in
+    /// their 11-digit form
+    pub fn from_concept(concept: &CodeableConcept) -> Vec<SourceCode> {
+        concept
+            .coding
+            .iter()
+            .filter_map(|coding| {
+                let system = coding.system.as_deref()?;
+                let code = coding.code.as_deref()?.trim();
+                let vocabulary_id = TerminologyService::omop_vocabulary_id(system)?;
+                let concept_code = if system == NDC_SYSTEM {
+                    TerminologyService::normalize_ndc(code)?
+                } else {
+                    code.to_string()
+                };
+                Some(SourceCode { vocabulary_id: vocabulary_id.to_string(), concept_code })
+            })
+            .collect()
+    }
+}
+
+/// The concept of a source code, and the standard concept it maps to; 0
+/// where it maps to none
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OmopConcept {
+    pub concept_id: i32,
+    pub standard_concept_id: i32,
+}
+
+/// Concepts of source codes, as loaded from the OMOP vocabularies
+#[derive(Debug, Clone, Default)]
+pub struct OmopVocabulary {
+    concepts: HashMap<SourceCode, OmopConcept>,
+}
+
+impl OmopVocabulary {
+    pub fn insert(&mut self, code: SourceCode, concept: OmopConcept) {
+        self.concepts.insert(code, concept);
+    }
+
+    /// The concept of a code, if the vocabularies know it
+    pub fn concept(&self, code: &SourceCode) -> Option<OmopConcept> {
+        self.concepts.get(code).copied()
+    }
+}
+
+/// A record's concept: the standard concept of the first of its codes that
+/// maps to one, else 0 and the first code as it was recorded
+#[derive(Debug, Clone, PartialEq)]
+struct Mapped {
+    concept_id: i32,
+    source_concept_id: i32,
+    source_value: String,
+}
+
+fn map_codes(vocabulary: &OmopVocabulary, codes: &[SourceCode], unmapped: &mut BTreeMap<SourceCode, usize>) -> Mapped {
+    let standard = codes.iter().find_map(|code| {
+        vocabulary
+            .concept(code)
+            .filter(|concept| concept.standard_concept_id != 0)
+            .map(|concept| (code, concept))
+    });
+    match standard {
+        Some((code, concept)) => Mapped {
+            concept_id: concept.standard_concept_id,
+            source_concept_id: concept.concept_id,
+            source_value: code.concept_code.clone(),
+        },
+        None => {
+            if let Some(code) = codes.first() {
+                *unmapped.entry(code.clone()).or_default() += 1;
+            }
+            Mapped {
+                concept_id: 0,
+                source_concept_id: codes
+                    .first()
+                    .and_then(|code| vocabulary.concept(code))
+                    .map(|concept| concept.concept_id)
+                    .unwrap_or(0),
+                source_value: codes.first().map(|code| code.concept_code.clone()).unwrap_or_default(),
+            }
+        }
+    }
+}
+
+/// A patient, by the pseudonymous key of their record
+#[derive(Debug, Clone)]
+pub struct OmopPersonSource {
+    pub key: String,
+    pub gender: Option<String>,
+    pub birth_date: Option<NaiveDate>,
+}
+
+/// An encounter, its FHIR class code telling the kind of visit
+#[derive(Debug, Clone)]
+pub struct OmopVisitSource {
+    pub key: String,
+    pub person_key: String,
+    pub class_code: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// A diagnosis or problem, from onset (or when recorded) to abatement
+#[derive(Debug, Clone)]
+pub struct OmopConditionSource {
+    pub key: String,
+    pub person_key: String,
+    pub visit_key: Option<String>,
+    pub codes: Vec<SourceCode>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// A medication ordered, with what was to be dispensed
+#[derive(Debug, Clone)]
+pub struct OmopDrugSource {
+    pub key: String,
+    pub person_key: String,
+    pub visit_key: Option<String>,
+    pub codes: Vec<SourceCode>,
+    pub start: DateTime<Utc>,
+    pub quantity: Option<f64>,
+    pub days_supply: Option<i32>,
+    pub refills: Option<i32>,
+}
+
+/// A laboratory result or vital sign
+#[derive(Debug, Clone)]
+pub struct OmopMeasurementSource {
+    pub key: String,
+    pub person_key: String,
+    pub visit_key: Option<String>,
+    pub codes: Vec<SourceCode>,
+    pub at: DateTime<Utc>,
+    pub value: Option<f64>,
+    /// UCUM code of the value's unit
+    pub unit: Option<String>,
+}
+
+/// Records to export, keyed by pseudonyms so no record ID leaves the system
+#[derive(Debug, Clone, Default)]
+pub struct OmopSource {
+    pub persons: Vec<OmopPersonSource>,
+    pub visits: Vec<OmopVisitSource>,
+    pub conditions: Vec<OmopConditionSource>,
+    pub drugs: Vec<OmopDrugSource>,
+    pub measurements: Vec<OmopMeasurementSource>,
+}
+
+impl OmopSource {
+    /// Every code the records carry, for the concepts to look up
+    pub fn codes(&self) -> HashSet<SourceCode> {
+        let mut codes: HashSet<SourceCode> = HashSet::new();
+        codes.extend(self.conditions.iter().flat_map(|record| record.codes.iter().cloned()));
+        codes.extend(self.drugs.iter().flat_map(|record| record.codes.iter().cloned()));
+        codes.extend(self.measurements.iter().flat_map(|record| record.codes.iter().cloned()));
+        codes.extend(
+            self.measurements
+                .iter()
+                .filter_map(|record| record.unit.as_ref())
+                .map(|unit| SourceCode { vocabulary_id: "UCUM".to_string(), concept_code: unit.clone() }),
+        );
+        codes
+    }
+}
+
+/// The CDM tables of an export, and the source codes that mapped to no
+/// standard concept with how often each was used
+#[derive(Debug, Clone, Default)]
+pub struct OmopExtract {
+    pub tables: Vec<(&'static str, Table)>,
+    pub unmapped: BTreeMap<SourceCode, usize>,
+}
+
+impl OmopExtract {
+    /// The tables as a ZIP archive of CSV files named after them
+    pub fn to_zip(&self) -> Vec<u8> {
+        let files: Vec<(String, Vec<u8>)> = self
+            .tables
+            .iter()
+            .map(|(name, table)| (format!("{}.csv", name), table.to_csv().into_bytes()))
+            .collect();
+        let parts: Vec<(&str, &[u8])> = files.iter().map(|(name, data)| (name.as_str(), data.as_slice())).collect();
+        zip_archive(&parts)
+    }
+}
+
+/// The CDM ID of a record: the first eight bytes of the SHA-256 of its
+/// key, positive. Stable across exports, so a warehouse can reload them.
+pub fn omop_id(key: &str) -> i64 {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(bytes) >> 1) as i64
+}
+
+/// The visit concept of a FHIR encounter class
+pub fn omop_visit_concept(class_code: Option<&str>) -> i32 {
+    match class_code {
+        Some("IMP" | "ACUTE" | "NONAC") => OMOP_INPATIENT_VISIT,
+        Some("AMB" | "VR") => OMOP_OUTPATIENT_VISIT,
+        Some("EMER") => OMOP_EMERGENCY_VISIT,
+        Some("HH") => OMOP_HOME_VISIT,
+        _ => 0,
+    }
+}
+
+fn table(columns: &[&str]) -> Table {
+    Table { columns: columns.iter().map(|column| column.to_string()).collect(), rows: Vec::new() }
+}
+
+fn id(key: &str) -> Cell {
+    Cell::Text(omop_id(key).to_string())
+}
+
+fn optional_id(key: Option<&String>) -> Cell {
+    key.map(|key| id(key)).unwrap_or(Cell::Empty)
+}
+
+fn concept(concept_id: i32) -> Cell {
+    Cell::Number(f64::from(concept_id))
+}
+
+fn text(value: &str) -> Cell {
+    if value.is_empty() {
+        Cell::Empty
+    } else {
+        Cell::Text(value.to_string())
+    }
+}
+
+fn date(at: NaiveDate) -> Cell {
+    Cell::Text(at.format("%Y-%m-%d").to_string())
+}
+
+fn datetime(at: DateTime<Utc>) -> Cell {
+    Cell::Text(at.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// Widen a person's observation period to take in a day
+fn observe(periods: &mut HashMap<String, (NaiveDate, NaiveDate)>, person_key: &str, day: NaiveDate) {
+    periods
+        .entry(person_key.to_string())
+        .and_modify(|(start, end)| {
+            *start = (*start).min(day);
+            *end = (*end).max(day);
+        })
+        .or_insert((day, day));
+}
+
+/// Map records to the CDM's person, observation_period, visit_occurrence,
+/// condition_occurrence, drug_exposure and measurement tables. A person
+/// needs a year of birth, so patients without a birth date are left out,
+/// and with them their records, as are references to visits not exported.
+/// A person is observed from their first record to their last, and left
+/// out if they have none. Dates are in UTC.
+pub fn omop_extract(source: &OmopSource, vocabulary: &OmopVocabulary) -> OmopExtract {
+    let mut unmapped = BTreeMap::new();
+    let mut periods: HashMap<String, (NaiveDate, NaiveDate)> = HashMap::new();
+
+    let persons: HashSet<&str> = source
+        .persons
+        .iter()
+        .filter(|record| record.birth_date.is_some())
+        .map(|record| record.key.as_str())
+        .collect();
+
+    let mut visit_occurrence = table(&[
+        "visit_occurrence_id", "person_id", "visit_concept_id", "visit_start_date", "visit_start_datetime",
+        "visit_end_date", "visit_end_datetime", "visit_type_concept_id", "provider_id", "care_site_id",
+        "visit_source_value", "visit_source_concept_id", "admitted_from_concept_id", "admitted_from_source_value",
+        "discharged_to_concept_id", "discharged_to_source_value", "preceding_visit_occurrence_id",
+    ]);
+    let mut visits: HashSet<&str> = HashSet::new();
+    for record in source.visits.iter().filter(|record| persons.contains(record.person_key.as_str())) {
+        visits.insert(record.key.as_str());
+        // A visit still open ends, as far as the CDM is concerned, the day it started
+        let end = record.end.unwrap_or(record.start);
+        observe(&mut periods, &record.person_key, record.start.date_naive());
+        observe(&mut periods, &record.person_key, end.date_naive());
+        visit_occurrence.rows.push(vec![
+            id(&record.key),
+            id(&record.person_key),
+            concept(omop_visit_concept(record.class_code.as_deref())),
+            date(record.start.date_naive()),
+            datetime(record.start),
+            date(end.date_naive()),
+            datetime(end),
+            concept(OMOP_EHR_TYPE),
+            Cell::Empty,
+            Cell::Empty,
+            text(record.class_code.as_deref().unwrap_or_default()),
+            concept(0),
+            concept(0),
+            Cell::Empty,
+            concept(0),
+            Cell::Empty,
+            Cell::Empty,
+        ]);
+    }
+    let visit = |key: Option<&String>| optional_id(key.filter(|key| visits.contains(key.as_str())));
+
+    let mut condition_occurrence = table(&[
+        "condition_occurrence_id", "person_id", "condition_concept_id", "condition_start_date",
+        "condition_start_datetime", "condition_end_date", "condition_end_datetime", "condition_type_concept_id",
+        "condition_status_concept_id", "stop_reason", "provider_id", "visit_occurrence_id", "visit_detail_id",
+        "condition_source_value", "condition_source_concept_id", "condition_status_source_value",
+    ]);
+    for record in source.conditions.iter().filter(|record| persons.contains(record.person_key.as_str())) {
+        let mapped = map_codes(vocabulary, &record.codes, &mut unmapped);
+        observe(&mut periods, &record.person_key, record.start.date_naive());
+        condition_occurrence.rows.push(vec![
+            id(&record.key),
+            id(&record.person_key),
+            concept(mapped.concept_id),
+            date(record.start.date_naive()),
+            datetime(record.start),
+            record.end.map(|end| date(end.date_naive())).unwrap_or(Cell::Empty),
+            record.end.map(datetime).unwrap_or(Cell::Empty),
+            concept(OMOP_EHR_TYPE),
+            concept(0),
+            Cell::Empty,
+            Cell::Empty,
+            visit(record.visit_key.as_ref()),
+            Cell::Empty,
+            text(&mapped.source_value),
+            concept(mapped.source_concept_id),
+            Cell::Empty,
+        ]);
+    }
+
+    let mut drug_exposure = table(&[
+        "drug_exposure_id", "person_id", "drug_concept_id", "drug_exposure_start_date",
+        "drug_exposure_start_datetime", "drug_exposure_end_date", "drug_exposure_end_datetime",
+        "verbatim_end_date", "drug_type_concept_id", "stop_reason", "refills", "quantity", "days_supply", "sig",
+        "route_concept_id", "lot_number", "provider_id", "visit_occurrence_id", "visit_detail_id",
+        "drug_source_value", "drug_source_concept_id", "route_source_value", "dose_unit_source_value",
+    ]);
+    for record in source.drugs.iter().filter(|record| persons.contains(record.person_key.as_str())) {
+        let mapped = map_codes(vocabulary, &record.codes, &mut unmapped);
+        let start = record.start.date_naive();
+        // The CDM's convention: the supply runs out after its days, and an
+        // exposure of unknown length ends the day it starts
+        let end = match record.days_supply {
+            Some(days) if days > 0 => start + Duration::days(i64::from(days) - 1),
+            _ => start,
+        };
+        observe(&mut periods, &record.person_key, start);
+        drug_exposure.rows.push(vec![
+            id(&record.key),
+            id(&record.person_key),
+            concept(mapped.concept_id),
+            date(start),
+            datetime(record.start),
+            date(end),
+            Cell::Empty,
+            Cell::Empty,
+            concept(OMOP_EHR_TYPE),
+            Cell::Empty,
+            record.refills.map(|refills| Cell::Number(f64::from(refills))).unwrap_or(Cell::Empty),
+            record.quantity.map(Cell::Number).unwrap_or(Cell::Empty),
+            record.days_supply.map(|days| Cell::Number(f64::from(days))).unwrap_or(Cell::Empty),
+            Cell::Empty,
+            concept(0),
+            Cell::Empty,
+            Cell::Empty,
+            visit(record.visit_key.as_ref()),
+            Cell::Empty,
+            text(&mapped.source_value),
+            concept(mapped.source_concept_id),
+            Cell::Empty,
+            Cell::Empty,
+        ]);
+    }
+
+    let mut measurement = table(&[
+        "measurement_id", "person_id", "measurement_concept_id", "measurement_date", "measurement_datetime",
+        "measurement_time", "measurement_type_concept_id", "operator_concept_id", "value_as_number",
+        "value_as_concept_id", "unit_concept_id", "range_low", "range_high", "provider_id", "visit_occurrence_id",
+        "visit_detail_id", "measurement_source_value", "measurement_source_concept_id", "unit_source_value",
+        "unit_source_concept_id", "value_source_value", "measurement_event_id", "meas_event_field_concept_id",
+    ]);
+    for record in source.measurements.iter().filter(|record| persons.contains(record.person_key.as_str())) {
+        let mapped = map_codes(vocabulary, &record.codes, &mut unmapped);
+        let unit = record.unit.as_ref().map(|unit| SourceCode {
+            vocabulary_id: "UCUM".to_string(),
+            concept_code: unit.clone(),
+        });
+        // UCUM units are standard concepts themselves
+        let unit_concept = unit.as_ref().and_then(|unit| vocabulary.concept(unit)).map(|unit| unit.concept_id);
+        observe(&mut periods, &record.person_key, record.at.date_naive());
+        measurement.rows.push(vec![
+            id(&record.key),
+            id(&record.person_key),
+            concept(mapped.concept_id),
+            date(record.at.date_naive()),
+            datetime(record.at),
+            Cell::Empty,
+            concept(OMOP_EHR_TYPE),
+            Cell::Empty,
+            record.value.map(Cell::Number).unwrap_or(Cell::Empty),
+            Cell::Empty,
+            concept(unit_concept.unwrap_or(0)),
+            Cell::Empty,
+            Cell::Empty,
+            Cell::Empty,
+            visit(record.visit_key.as_ref()),
+            Cell::Empty,
+            text(&mapped.source_value),
+            concept(mapped.source_concept_id),
+            text(record.unit.as_deref().unwrap_or_default()),
+            concept(unit_concept.unwrap_or(0)),
+            record.value.map(|value| Cell::Text(value.to_string())).unwrap_or(Cell::Empty),
+            Cell::Empty,
+            Cell::Empty,
+        ]);
+    }
+
+    let mut person = table(&[
+        "person_id", "gender_concept_id", "year_of_birth", "month_of_birth", "day_of_birth", "birth_datetime",
+        "race_concept_id", "ethnicity_concept_id", "location_id", "provider_id", "care_site_id",
+        "person_source_value", "gender_source_value", "gender_source_concept_id", "race_source_value",
+        "race_source_concept_id", "ethnicity_source_value", "ethnicity_source_concept_id",
+    ]);
+    // Persons with nothing exported are left out, having no observation period
+    for record in source.persons.iter().filter(|record| periods.contains_key(&record.key)) {
+        let Some(birth_date) = record.birth_date else {
+            continue;
+        };
+        let gender = match record.gender.as_deref() {
+            Some("male") => OMOP_MALE,
+            Some("female") => OMOP_FEMALE,
+            _ => 0,
+        };
+        person.rows.push(vec![
+            id(&record.key),
+            concept(gender),
+            Cell::Number(f64::from(birth_date.year())),
+            Cell::Number(f64::from(birth_date.month())),
+            Cell::Number(f64::from(birth_date.day())),
+            Cell::Empty,
+            concept(0),
+            concept(0),
+            Cell::Empty,
+            Cell::Empty,
+            Cell::Empty,
+            text(&record.key),
+            text(record.gender.as_deref().unwrap_or_default()),
+            concept(0),
+            Cell::Empty,
+            concept(0),
+            Cell::Empty,
+            concept(0),
+        ]);
+    }
+
+    let mut observation_period = table(&[
+        "observation_period_id", "person_id", "observation_period_start_date", "observation_period_end_date",
+        "period_type_concept_id",
+    ]);
+    let mut periods: Vec<(String, (NaiveDate, NaiveDate))> = periods.into_iter().collect();
+    periods.sort();
+    for (person_key, (start, end)) in periods {
+        observation_period.rows.push(vec![
+            id(&format!("{}/observation_period", person_key)),
+            id(&person_key),
+            date(start),
+            date(end),
+            concept(OMOP_EHR_TYPE),
+        ]);
+    }
+
+    OmopExtract {
+        tables: vec![
+            ("person", person),
+            ("observation_period", observation_period),
+            ("visit_occurrence", visit_occurrence),
+            ("condition_occurrence", condition_occurrence),
+            ("drug_exposure", drug_exposure),
+            ("measurement", measurement),
+        ],
+        unmapped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::constants::ICD_10_CM_SYSTEM;
+    use crate::models::Coding;
+    use chrono::TimeZone;
+
+    fn code(vocabulary_id: &str, concept_code: &str) -> SourceCode {
+        SourceCode { vocabulary_id: vocabulary_id.to_string(), concept_code: concept_code.to_string() }
+    }
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, 9, 30, 0).unwrap()
+    }
+
+    fn column<'a>(extract: &'a OmopExtract, table: &str, row: usize, column: &str) -> &'a Cell {
+        let (_, table) = extract.tables.iter().find(|(name, _)| *name == table).unwrap();
+        let index = table.columns.iter().position(|name| name == column).unwrap();
+        &table.rows[row][index]
+    }
+
+    #[test]
+    fn test_source_codes() {
+        let concept = CodeableConcept {
+            coding: vec![
+                Coding {
+                    system: Some(ICD_10_CM_SYSTEM.to_string()),
+                    version: None,
+                    code: Some("E11.9".to_string()),
+                    display: None,
+                },
+                Coding {
+                    system: Some(NDC_SYSTEM.to_string()),
+                    version: None,
+                    code: Some("0002-1433-80".to_string()),
+                    display: None,
+                },
+                Coding {
+                    system: Some("http://example.org/local".to_string()),
+                    version: None,
+                    code: Some("X1".to_string()),
+                    display: None,
+                },
+            ],
+            text: None,
+        };
+        assert_eq!(
+            SourceCode::from_concept(&concept),
+            vec![code("ICD10CM", "E11.9"), code("NDC", "00002143380")]
+        );
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut vocabulary = OmopVocabulary::default();
+        // ICD-10-CM E11.9 maps to SNOMED type 2 diabetes mellitus
+        vocabulary.insert(code("ICD10CM", "E11.9"), OmopConcept { concept_id: 45576876, standard_concept_id: 201826 });
+        vocabulary.insert(code("LOINC", "8867-4"), OmopConcept { concept_id: 3027018, standard_concept_id: 3027018 });
+        vocabulary.insert(code("UCUM", "/min"), OmopConcept { concept_id: 8541, standard_concept_id: 8541 });
+
+        let source = OmopSource {
+            persons: vec![
+                OmopPersonSource {
+                    key: "p1".to_string(),
+                    gender: Some("female".to_string()),
+                    birth_date: NaiveDate::from_ymd_opt(1970, 5, 17),
+                },
+                OmopPersonSource { key: "p2".to_string(), gender: Some("male".to_string()), birth_date: None },
+            ],
+            visits: vec![OmopVisitSource {
+                key: "v1".to_string(),
+                person_key: "p1".to_string(),
+                class_code: Some("IMP".to_string()),
+                start: at(1),
+                end: Some(at(4)),
+            }],
+            conditions: vec![
+                OmopConditionSource {
+                    key: "c1".to_string(),
+                    person_key: "p1".to_string(),
+                    visit_key: Some("v1".to_string()),
+                    codes: vec![code("ICD10CM", "E11.9")],
+                    start: at(2),
+                    end: None,
+                },
+                OmopConditionSource {
+                    key: "c2".to_string(),
+                    person_key: "p2".to_string(),
+                    visit_key: None,
+                    codes: vec![code("ICD10CM", "I10")],
+                    start: at(2),
+                    end: None,
+                },
+            ],
+            drugs: vec![OmopDrugSource {
+                key: "d1".to_string(),
+                person_key: "p1".to_string(),
+                visit_key: Some("v9".to_string()),
+                codes: vec![code("RxNorm", "860975")],
+                start: at(4),
+                quantity: Some(60.0),
+                days_supply: Some(30),
+                refills: Some(2),
+            }],
+            measurements: vec![OmopMeasurementSource {
+                key: "m1".to_string(),
+                person_key: "p1".to_string(),
+                visit_key: Some("v1".to_string()),
+                codes: vec![code("LOINC", "8867-4")],
+                at: at(3),
+                value: Some(72.0),
+                unit: Some("/min".to_string()),
+            }],
+        };
+        assert!(source.codes().contains(&code("UCUM", "/min")));
+
+        let extract = omop_extract(&source, &vocabulary);
+        let rows = |name: &str| extract.tables.iter().find(|(table, _)| *table == name).unwrap().1.rows.len();
+        // The patient without a birth date is left out, and their condition with them
+        assert_eq!(rows("person"), 1);
+        assert_eq!(rows("condition_occurrence"), 1);
+        assert_eq!(column(&extract, "person", 0, "gender_concept_id"), &Cell::Number(8532.0));
+        assert_eq!(column(&extract, "person", 0, "year_of_birth"), &Cell::Number(1970.0));
+        assert_eq!(column(&extract, "visit_occurrence", 0, "visit_concept_id"), &Cell::Number(9201.0));
+
+        assert_eq!(column(&extract, "condition_occurrence", 0, "condition_concept_id"), &Cell::Number(201826.0));
+        let source_concept = column(&extract, "condition_occurrence", 0, "condition_source_concept_id");
+        assert_eq!(source_concept, &Cell::Number(45576876.0));
+        assert_eq!(column(&extract, "condition_occurrence", 0, "visit_occurrence_id"), &id("v1"));
+
+        // Unmapped, its code kept; a visit not exported is not referenced
+        assert_eq!(column(&extract, "drug_exposure", 0, "drug_concept_id"), &Cell::Number(0.0));
+        assert_eq!(column(&extract, "drug_exposure", 0, "drug_source_value"), &Cell::Text("860975".to_string()));
+        // Thirty days' supply from 4 March runs out on 2 April
+        let end = NaiveDate::from_ymd_opt(2024, 4, 2).unwrap();
+        assert_eq!(column(&extract, "drug_exposure", 0, "drug_exposure_end_date"), &date(end));
+        assert_eq!(column(&extract, "drug_exposure", 0, "visit_occurrence_id"), &Cell::Empty);
+        assert_eq!(extract.unmapped.get(&code("RxNorm", "860975")), Some(&1));
+
+        assert_eq!(column(&extract, "measurement", 0, "unit_concept_id"), &Cell::Number(8541.0));
+        let period = |name: &str| column(&extract, "observation_period", 0, name).clone();
+        assert_eq!(period("observation_period_start_date"), date(at(1).date_naive()));
+        assert_eq!(period("observation_period_end_date"), date(at(4).date_naive()));
+
+        assert!(omop_id("p1") > 0);
+        assert_ne!(omop_id("p1"), omop_id("p2"));
+        assert!(extract.to_zip().starts_with(b"PK\x03\x04"));
+    }
+}
@@ -1,42 +1,324 @@
-// Placeholder implementations for exporters
+// PDF documents
 
+#[allow(clippy::module_inception)]
 pub mod pdf {
+    /// A4, in points
+    const PAGE_WIDTH: f32 = 595.0;
+    const PAGE_HEIGHT: f32 = 842.0;
+    const MARGIN: f32 = 56.0;
+
+    /// Font resource, size, leading and characters per line of each kind
+    /// of text. Lines are wrapped by count on an average Helvetica width.
+    const HEADING: (&str, f32, f32, usize) = ("F2", 13.0, 22.0, 62);
+    const BODY: (&str, f32, f32, usize) = ("F1", 10.5, 14.0, 84);
+
+    #[derive(Debug, Clone)]
+    enum Block {
+        Heading(String),
+        Paragraph(String),
+        Space,
+        Table { columns: Vec<String>, rows: Vec<Vec<String>> },
+    }
+
+    /// A plain text document of headings, paragraphs and tables, rendered as a PDF
+    /// in Helvetica on as many A4 pages as it needs. Characters outside
+    /// Latin-1 print as `?`.
+    #[derive(Debug, Clone)]
+    pub struct PdfDocument {
+        title: String,
+        blocks: Vec<Block>,
+    }
+
+    impl PdfDocument {
+        /// An empty document; the title goes in its properties, not on the
+        /// page
+        pub fn new(title: impl Into<String>) -> Self {
+            Self {
+                title: title.into(),
+                blocks: Vec::new(),
+            }
+        }
+
+        pub fn heading(&mut self, text: impl Into<String>) -> &mut Self {
+            self.blocks.push(Block::Heading(text.into()));
+            self
+        }
+
+        /// A paragraph, wrapped to the page; newlines start new lines
+        pub fn paragraph(&mut self, text: impl Into<String>) -> &mut Self {
+            self.blocks.push(Block::Paragraph(text.into()));
+            self
+        }
+
+        /// A blank line
+        pub fn space(&mut self) -> &mut Self {
+            self.blocks.push(Block::Space);
+            self
+        }
+
+        /// A table of equally wide columns, a line per row, its header in
+        /// bold and repeated on each page it continues on. Values too wide
+        /// for their column are cut short with `...`.
+        pub fn table(&mut self, columns: Vec<String>, rows: Vec<Vec<String>>) -> &mut Self {
+            self.blocks.push(Block::Table { columns, rows });
+            self
+        }
+
+        /// The document as PDF 1.4
+        pub fn render(&self) -> Vec<u8> {
+            let pages: Vec<_> = self.pages().into_iter().map(|content| (PAGE_WIDTH, PAGE_HEIGHT, content)).collect();
+            assemble(&self.title, &pages)
+        }
+
+        /// Content stream of each page, at least one
+        fn pages(&self) -> Vec<Vec<u8>> {
+            let mut pages = Vec::new();
+            let mut content = b"BT\n".to_vec();
+            let mut y = PAGE_HEIGHT - MARGIN;
+            // Ends the page if fewer than `leading` points are left on it
+            let mut next_line = |content: &mut Vec<u8>, y: &mut f32, leading: f32| {
+                let full = *y - leading < MARGIN;
+                if full {
+                    content.extend_from_slice(b"ET");
+                    pages.push(std::mem::replace(content, b"BT\n".to_vec()));
+                    *y = PAGE_HEIGHT - MARGIN;
+                }
+                *y -= leading;
+                full
+            };
+
+            for block in &self.blocks {
+                let ((font, size, leading, width), text) = match block {
+                    Block::Heading(text) => (HEADING, text.as_str()),
+                    Block::Paragraph(text) => (BODY, text.as_str()),
+                    Block::Space => {
+                        y -= BODY.2;
+                        continue;
+                    }
+                    Block::Table { columns, rows } => {
+                        let column_width = (PAGE_WIDTH - 2.0 * MARGIN) / columns.len().max(1) as f32;
+                        let characters = (BODY.3 / columns.len().max(1)).saturating_sub(1).max(1);
+                        let row_line = |content: &mut Vec<u8>, font: &str, y: f32, row: &[String]| {
+                            for (i, value) in row.iter().take(columns.len()).enumerate() {
+                                let x = MARGIN + column_width * i as f32;
+                                show(content, (font, BODY.1), x, y, &fit(value, characters));
+                            }
+                        };
+                        next_line(&mut content, &mut y, BODY.2);
+                        row_line(&mut content, "F2", y, columns);
+                        for row in rows {
+                            if next_line(&mut content, &mut y, BODY.2) {
+                                row_line(&mut content, "F2", y, columns);
+                                next_line(&mut content, &mut y, BODY.2);
+                            }
+                            row_line(&mut content, BODY.0, y, row);
+                        }
+                        continue;
+                    }
+                };
+                for line in wrap(text, width) {
+                    next_line(&mut content, &mut y, leading);
+                    show(&mut content, (font, size), MARGIN, y, &line);
+                }
+            }
+            content.extend_from_slice(b"ET");
+            pages.push(content);
+            pages
+        }
+    }
+
+    /// A PDF 1.4 file of pages given as their width and height in points
+    /// and content stream, which may use Helvetica as `F1` and
+    /// Helvetica-Bold as `F2`
+    pub(crate) fn assemble(title: &str, pages: &[(f32, f32, Vec<u8>)]) -> Vec<u8> {
+        // Catalog, page tree, fonts and properties, then a page and its
+        // content stream for each page
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".as_bytes().to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..pages.len()).map(|i| format!("{} 0 R", 6 + 2 * i)).collect::<Vec<_>>().join(" "),
+                pages.len()
+            )
+            .into_bytes(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+            [b"<< /Title (".as_slice(), &text_string(title), b") /Producer (open-hims) >>"].concat(),
+        ];
+        for (i, (width, height, content)) in pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    width,
+                    height,
+                    7 + 2 * i
+                )
+                .into_bytes(),
+            );
+            objects.push(
+                [
+                    format!("<< /Length {} >>\nstream\n", content.len()).as_bytes(),
+                    content,
+                    b"\nendstream",
+                ]
+                .concat(),
+            );
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+
+    /// Show a line of text at a point
+    fn show(content: &mut Vec<u8>, (font, size): (&str, f32), x: f32, y: f32, text: &str) {
+        content.extend_from_slice(format!("/{} {} Tf\n1 0 0 1 {} {} Tm\n(", font, size, x, y).as_bytes());
+        content.extend_from_slice(&text_string(text));
+        content.extend_from_slice(b") Tj\n");
+    }
+
+    /// A value cut to at most `width` characters, ending `...` if it was cut
+    fn fit(value: &str, width: usize) -> String {
+        let line = value.lines().next().unwrap_or_default();
+        if line.chars().count() <= width && !value.contains('\n') {
+            return line.to_string();
+        }
+        let kept: String = line.chars().take(width.saturating_sub(3)).collect();
+        format!("{}...", kept)
+    }
+
+    /// Lines of at most `width` characters, broken between words where
+    /// possible
+    fn wrap(text: &str, width: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let mut word: Vec<char> = word.chars().collect();
+                let length = line.chars().count();
+                if length > 0 && length + 1 + word.len() > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                while word.len() > width {
+                    lines.push(word.drain(..width).collect());
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.extend(word);
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Bytes of a PDF literal string, without its parentheses
+    pub(crate) fn text_string(text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '(' | ')' | '\\' => bytes.extend_from_slice(&[b'\\', c as u8]),
+                '\t' => bytes.push(b' '),
+                c if (c as u32) < 0x20 || (0x7F..0xA0).contains(&(c as u32)) || (c as u32) > 0xFF => bytes.push(b'?'),
+                c => bytes.push(c as u32 as u8),
+            }
+        }
+        bytes
+    }
+
     pub struct PdfExporter;
     impl PdfExporter {
         pub fn export_patient_report(_patient_id: &str) -> Result<Vec<u8>, crate::core::HimsError> {
             Ok(vec![]) // Placeholder
         }
     }
-}
 
-pub mod x12_edi {
-    pub struct X12EdiExporter;
-    impl X12EdiExporter {
-        pub fn export_claim(_claim_data: &str) -> Result<String, crate::core::HimsError> {
-            Ok("EDI transaction".to_string()) // Placeholder
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_wrap() {
+            assert_eq!(wrap("one two three", 7), vec!["one two", "three"]);
+            assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+            assert_eq!(wrap("first\n\nthird", 80), vec!["first", "", "third"]);
         }
-    }
-}
 
-pub mod csv_fhir_import {
-    pub struct CsvFhirImporter;
-    impl CsvFhirImporter {
-        pub fn import_csv_to_fhir(_csv_data: &str) -> Result<String, crate::core::HimsError> {
-            Ok("{}".to_string()) // Placeholder
+        #[test]
+        fn test_table() {
+            assert_eq!(fit("short", 10), "short");
+            assert_eq!(fit("much too long", 10), "much to...");
+            assert_eq!(fit("two\nlines", 10), "two...");
+
+            let mut document = PdfDocument::new("Admissions");
+            let rows = (0..80).map(|i| vec![format!("Ward {}", i), i.to_string()]).collect();
+            document.heading("Admissions").table(vec!["Ward".to_string(), "Admitted".to_string()], rows);
+            let pages = document.pages();
+            assert_eq!(pages.len(), 2);
+            for page in &pages {
+                // Header first on each page, in bold
+                let page = String::from_utf8_lossy(page);
+                let header = page.find("(Admitted) Tj").unwrap();
+                assert!(page[..header].contains("/F2 10.5 Tf\n1 0 0 1 297.5 "));
+                assert!(!page[..header].contains("(Ward "));
+            }
+            let second = String::from_utf8_lossy(&pages[1]);
+            assert!(second.contains("1 0 0 1 297.5 "), "the second column starts halfway across");
+            assert!(second.contains("(Ward 79) Tj"));
         }
-    }
-}
 
-pub mod api_adapters {
-    pub struct ApiAdapter;
-    impl ApiAdapter {
-        pub fn sync_with_external_system(_data: &str) -> Result<String, crate::core::HimsError> {
-            Ok("Sync complete".to_string()) // Placeholder
+        #[test]
+        fn test_render() {
+            let mut document = PdfDocument::new("Referral (cardiology)");
+            document.heading("Referral letter").space();
+            for i in 0..100 {
+                document.paragraph(format!("Paragraph {} for Zoë \\ café ✓", i));
+            }
+            let pdf = document.render();
+            let contains = |needle: &[u8]| pdf.windows(needle.len()).any(|window| window == needle);
+
+            assert!(pdf.starts_with(b"%PDF-1.4\n"));
+            assert!(pdf.ends_with(b"%%EOF\n"));
+            assert!(contains(b"/Title (Referral \\(cardiology\\))"));
+            assert!(contains(b"(Paragraph 99 for Zo\xEB \\\\ caf\xE9 ?) Tj"));
+            assert!(contains(b"/Count 2 "), "100 paragraphs need a second page");
+
+            // The cross-reference table locates every object
+            let xref_at = pdf.windows(10).rposition(|window| window == b"startxref\n").unwrap();
+            let xref: usize = std::str::from_utf8(&pdf[xref_at + 10..]).unwrap().lines().next().unwrap().parse().unwrap();
+            let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+            assert!(table.starts_with("xref\n0 10\n"));
+            let offsets: Vec<usize> = table
+                .lines()
+                .filter(|line| line.ends_with(" 00000 n "))
+                .map(|line| line[..10].parse().unwrap())
+                .collect();
+            assert_eq!(offsets.len(), 9);
+            for (i, offset) in offsets.iter().enumerate() {
+                assert!(pdf[*offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+            }
         }
     }
 }
 
 pub use pdf::*;
-pub use x12_edi::*;
-pub use csv_fhir_import::*;
-pub use api_adapters::*;
\ No newline at end of file
@@ -0,0 +1,591 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::core::HimsError;
+use crate::countries::usa::states::UsStateRegistry;
+use crate::models::Gender;
+
+/// ASAP standard version the generator emits
+pub const ASAP_VERSION: &str = "4.2";
+
+const ELEMENT_SEPARATOR: char = '*';
+const SEGMENT_TERMINATOR: char = '~';
+
+/// DEA controlled substance schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeaSchedule {
+    II,
+    III,
+    IV,
+    V,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdmpPharmacy {
+    pub npi: String,
+    pub ncpdp_id: String,
+    pub dea_number: String,
+    pub name: String,
+    /// State the pharmacy dispenses in, which receives the report
+    pub state_code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdmpPatient {
+    pub id: String,
+    pub last_name: String,
+    pub first_name: String,
+    pub address_line: String,
+    pub city: String,
+    pub state_code: String,
+    pub postal_code: String,
+    pub birth_date: NaiveDate,
+    pub gender: Gender,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdmpPrescriber {
+    pub npi: String,
+    pub dea_number: String,
+    pub last_name: String,
+    pub first_name: String,
+}
+
+/// How the prescription reached the pharmacy (DSP12)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrescriptionOrigin {
+    Written,
+    Telephone,
+    Fax,
+    Electronic,
+    Transfer,
+}
+
+/// Payment type of the dispensation (DSP16)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PdmpPaymentType {
+    PrivatePay,
+    Medicaid,
+    Medicare,
+    CommercialInsurance,
+    Military,
+    WorkersCompensation,
+    Other,
+}
+
+/// A dispensed prescription to be reported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dispensation {
+    pub prescription_number: String,
+    pub refill_number: u32,
+    pub refills_authorized: u32,
+    pub written_date: NaiveDate,
+    pub dispensed_at: DateTime<Utc>,
+    pub ndc: String,
+    pub schedule: Option<DeaSchedule>,
+    pub quantity: f64,
+    pub days_supply: u32,
+    pub origin: PrescriptionOrigin,
+    pub payment_type: PdmpPaymentType,
+    pub patient: PdmpPatient,
+    pub prescriber: PdmpPrescriber,
+}
+
+impl Dispensation {
+    /// Key identifying the dispensation across reporting files
+    pub fn report_key(&self) -> String {
+        format!("{}:{}", self.prescription_number, self.refill_number)
+    }
+}
+
+/// A generated ASAP reporting file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdmpReportFile {
+    pub control_number: String,
+    pub state_code: String,
+    pub file_name: String,
+    pub content: String,
+    pub record_count: usize,
+    pub record_keys: Vec<String>,
+    pub generated_at: DateTime<Utc>,
+    /// Deadline of the oldest dispensation in the file
+    pub due_by: Option<DateTime<Utc>>,
+}
+
+/// Generates ASAP 4.2 PDMP reporting files using each state's
+/// prescription monitoring rules
+pub struct PdmpReportGenerator {
+    states: UsStateRegistry,
+}
+
+impl PdmpReportGenerator {
+    pub fn new(states: UsStateRegistry) -> Self {
+        Self { states }
+    }
+
+    /// Deadline for reporting a dispensation in the state
+    pub fn reporting_deadline(&self, state_code: &str, dispensed_at: DateTime<Utc>) -> Result<DateTime<Utc>, HimsError> {
+        let config = self.states.get_state_config(state_code)?;
+        Ok(dispensed_at + Duration::hours(config.prescription_monitoring.reporting_timeframe_hours as i64))
+    }
+
+    /// Build the reporting file for a pharmacy's dispensations. Only
+    /// controlled substances are included where the state limits its PDMP
+    /// to them; the file is empty of records when nothing is reportable.
+    pub fn generate(
+        &self,
+        pharmacy: &PdmpPharmacy,
+        dispensations: &[Dispensation],
+        control_number: &str,
+        now: DateTime<Utc>,
+    ) -> Result<PdmpReportFile, HimsError> {
+        let config = self.states.get_state_config(&pharmacy.state_code)?;
+        let monitoring = &config.prescription_monitoring;
+        if !monitoring.pdmp_required {
+            return Err(HimsError::ConfigurationError {
+                message: format!("{} does not require PDMP reporting", config.state_name),
+            });
+        }
+        if control_number.is_empty() || control_number.len() > 9 || !control_number.chars().all(|c| c.is_ascii_digit()) {
+            return Err(HimsError::ValidationError {
+                message: "PDMP control number must be 1-9 digits".to_string(),
+            });
+        }
+
+        let reportable: Vec<&Dispensation> = dispensations
+            .iter()
+            .filter(|d| !monitoring.controlled_substances_only || d.schedule.is_some())
+            .collect();
+
+        let mut segments = vec![
+            segment(&[
+                "TH",
+                ASAP_VERSION,
+                control_number,
+                "01",
+                "",
+                &now.format("%Y%m%d").to_string(),
+                &now.format("%H%M%S").to_string(),
+                "P",
+                "",
+                &SEGMENT_TERMINATOR.to_string(),
+            ]),
+            segment(&["IS", &pharmacy.ncpdp_id, &pharmacy.name]),
+        ];
+
+        let detail_start = segments.len();
+        segments.push(segment(&["PHA", &pharmacy.npi, &pharmacy.ncpdp_id, &pharmacy.dea_number]));
+        for dispensation in &reportable {
+            segments.push(patient_segment(&dispensation.patient));
+            segments.push(dispensing_segment(dispensation));
+            segments.push(segment(&[
+                "PRE",
+                &dispensation.prescriber.npi,
+                &dispensation.prescriber.dea_number,
+                "",
+                "",
+                &dispensation.prescriber.last_name,
+                &dispensation.prescriber.first_name,
+            ]));
+        }
+        // TP01 counts from PHA through TP inclusive
+        let detail_count = segments.len() - detail_start + 1;
+        segments.push(segment(&["TP", &detail_count.to_string()]));
+        // TT02 counts from TH through TT inclusive
+        let total_count = segments.len() + 1;
+        segments.push(segment(&["TT", control_number, &total_count.to_string()]));
+
+        let due_by = reportable
+            .iter()
+            .map(|d| d.dispensed_at)
+            .min()
+            .map(|oldest| oldest + Duration::hours(monitoring.reporting_timeframe_hours as i64));
+
+        Ok(PdmpReportFile {
+            control_number: control_number.to_string(),
+            state_code: pharmacy.state_code.clone(),
+            file_name: format!(
+                "{}_{}_{}.dat",
+                pharmacy.state_code,
+                pharmacy.ncpdp_id,
+                now.format("%Y%m%d%H%M%S")
+            ),
+            content: segments.join("\n"),
+            record_count: reportable.len(),
+            record_keys: reportable.iter().map(|d| d.report_key()).collect(),
+            generated_at: now,
+            due_by,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    Generated,
+    Submitted { at: DateTime<Utc> },
+    Accepted { at: DateTime<Utc> },
+    Rejected { at: DateTime<Utc>, reason: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdmpSubmission {
+    pub control_number: String,
+    pub state_code: String,
+    pub file_name: String,
+    pub record_keys: Vec<String>,
+    pub due_by: Option<DateTime<Utc>>,
+    pub status: SubmissionStatus,
+}
+
+impl PdmpSubmission {
+    /// Submitted, or accepted, by its deadline
+    pub fn on_time(&self) -> bool {
+        let submitted_at = match &self.status {
+            SubmissionStatus::Submitted { at } | SubmissionStatus::Accepted { at } => *at,
+            _ => return false,
+        };
+        self.due_by.map(|due| submitted_at <= due).unwrap_or(true)
+    }
+}
+
+/// Tracks PDMP files through submission and finds dispensations at risk of
+/// missing their state's reporting window
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PdmpSubmissionTracker {
+    submissions: HashMap<String, PdmpSubmission>,
+}
+
+impl PdmpSubmissionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_generated(&mut self, file: &PdmpReportFile) {
+        self.submissions.insert(
+            file.control_number.clone(),
+            PdmpSubmission {
+                control_number: file.control_number.clone(),
+                state_code: file.state_code.clone(),
+                file_name: file.file_name.clone(),
+                record_keys: file.record_keys.clone(),
+                due_by: file.due_by,
+                status: SubmissionStatus::Generated,
+            },
+        );
+    }
+
+    pub fn mark_submitted(&mut self, control_number: &str, at: DateTime<Utc>) -> Result<(), HimsError> {
+        self.submission_mut(control_number)?.status = SubmissionStatus::Submitted { at };
+        Ok(())
+    }
+
+    pub fn mark_accepted(&mut self, control_number: &str, at: DateTime<Utc>) -> Result<(), HimsError> {
+        self.submission_mut(control_number)?.status = SubmissionStatus::Accepted { at };
+        Ok(())
+    }
+
+    /// A rejected file's records count as unreported again
+    pub fn mark_rejected(&mut self, control_number: &str, at: DateTime<Utc>, reason: &str) -> Result<(), HimsError> {
+        self.submission_mut(control_number)?.status = SubmissionStatus::Rejected {
+            at,
+            reason: reason.to_string(),
+        };
+        Ok(())
+    }
+
+    pub fn get_submission(&self, control_number: &str) -> Option<&PdmpSubmission> {
+        self.submissions.get(control_number)
+    }
+
+    pub fn list_submissions(&self) -> Vec<&PdmpSubmission> {
+        let mut submissions: Vec<&PdmpSubmission> = self.submissions.values().collect();
+        submissions.sort_by(|a, b| a.control_number.cmp(&b.control_number));
+        submissions
+    }
+
+    /// Whether the dispensation is in a file that was submitted and not rejected
+    pub fn is_reported(&self, dispensation: &Dispensation) -> bool {
+        let key = dispensation.report_key();
+        self.submissions.values().any(|submission| {
+            matches!(
+                submission.status,
+                SubmissionStatus::Submitted { .. } | SubmissionStatus::Accepted { .. }
+            ) && submission.record_keys.contains(&key)
+        })
+    }
+
+    /// Unreported dispensations whose deadline has passed
+    pub fn overdue<'a>(
+        &self,
+        generator: &PdmpReportGenerator,
+        state_code: &str,
+        dispensations: &'a [Dispensation],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<&'a Dispensation>, HimsError> {
+        let mut overdue = Vec::new();
+        for dispensation in dispensations {
+            if self.is_reported(dispensation) {
+                continue;
+            }
+            if generator.reporting_deadline(state_code, dispensation.dispensed_at)? < now {
+                overdue.push(dispensation);
+            }
+        }
+        Ok(overdue)
+    }
+
+    fn submission_mut(&mut self, control_number: &str) -> Result<&mut PdmpSubmission, HimsError> {
+        self.submissions.get_mut(control_number).ok_or_else(|| HimsError::ValidationError {
+            message: format!("PDMP submission {} not found", control_number),
+        })
+    }
+}
+
+fn patient_segment(patient: &PdmpPatient) -> String {
+    let gender = match patient.gender {
+        Gender::Female => "F",
+        Gender::Male => "M",
+        Gender::Other | Gender::Unknown => "U",
+    };
+    segment(&[
+        "PAT",
+        "",
+        "",
+        &patient.id,
+        "",
+        "",
+        "",
+        &patient.last_name,
+        &patient.first_name,
+        "",
+        "",
+        "",
+        &patient.address_line,
+        "",
+        &patient.city,
+        &patient.state_code,
+        &patient.postal_code,
+        "",
+        &patient.birth_date.format("%Y%m%d").to_string(),
+        gender,
+        "01",
+    ])
+}
+
+fn dispensing_segment(dispensation: &Dispensation) -> String {
+    let origin = match dispensation.origin {
+        PrescriptionOrigin::Written => "01",
+        PrescriptionOrigin::Telephone => "02",
+        PrescriptionOrigin::Fax => "03",
+        PrescriptionOrigin::Electronic => "04",
+        PrescriptionOrigin::Transfer => "05",
+    };
+    let payment = match dispensation.payment_type {
+        PdmpPaymentType::PrivatePay => "01",
+        PdmpPaymentType::Medicaid => "02",
+        PdmpPaymentType::Medicare => "03",
+        PdmpPaymentType::CommercialInsurance => "04",
+        PdmpPaymentType::Military => "05",
+        PdmpPaymentType::WorkersCompensation => "06",
+        PdmpPaymentType::Other => "99",
+    };
+    segment(&[
+        "DSP",
+        "00",
+        &dispensation.prescription_number,
+        &dispensation.written_date.format("%Y%m%d").to_string(),
+        &dispensation.refills_authorized.to_string(),
+        &dispensation.dispensed_at.format("%Y%m%d").to_string(),
+        &dispensation.refill_number.to_string(),
+        "01",
+        &dispensation.ndc.replace('-', ""),
+        &format_quantity(dispensation.quantity),
+        &dispensation.days_supply.to_string(),
+        "01",
+        origin,
+        "00",
+        "",
+        "",
+        payment,
+    ])
+}
+
+fn format_quantity(quantity: f64) -> String {
+    if quantity.fract() == 0.0 {
+        format!("{}", quantity as u64)
+    } else {
+        format!("{:.3}", quantity).trim_end_matches('0').to_string()
+    }
+}
+
+/// Join elements into a segment, stripping delimiter characters from values
+fn segment(elements: &[&str]) -> String {
+    let (id, values) = elements.split_first().expect("segment has an identifier");
+    let mut segment = id.to_string();
+    for (index, value) in values.iter().enumerate() {
+        segment.push(ELEMENT_SEPARATOR);
+        // TH09 carries the segment terminator itself
+        if *id == "TH" && index == values.len() - 1 {
+            segment.push_str(value);
+        } else {
+            segment.extend(value.chars().filter(|c| *c != ELEMENT_SEPARATOR && *c != SEGMENT_TERMINATOR));
+        }
+    }
+    segment.push(SEGMENT_TERMINATOR);
+    segment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// ASAP 4.2 file expected for `batch()`, reviewed segment by segment
+    /// against the standard
+    const GOLDEN_BATCH: &str = include_str!("testdata/pdmp_asap42_batch.dat");
+
+    fn pharmacy(state_code: &str) -> PdmpPharmacy {
+        PdmpPharmacy {
+            npi: "1234567893".to_string(),
+            ncpdp_id: "0123456".to_string(),
+            dea_number: "FM1234563".to_string(),
+            name: "Main Street Pharmacy".to_string(),
+            state_code: state_code.to_string(),
+        }
+    }
+
+    fn dispensation(prescription_number: &str, refill_number: u32, schedule: Option<DeaSchedule>) -> Dispensation {
+        Dispensation {
+            prescription_number: prescription_number.to_string(),
+            refill_number,
+            refills_authorized: 2,
+            written_date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            dispensed_at: Utc.with_ymd_and_hms(2024, 3, 4, 15, 30, 0).unwrap(),
+            ndc: "00406-0512-01".to_string(),
+            schedule,
+            quantity: 30.0,
+            days_supply: 10,
+            origin: PrescriptionOrigin::Electronic,
+            payment_type: PdmpPaymentType::CommercialInsurance,
+            patient: PdmpPatient {
+                id: "MRN-55012".to_string(),
+                last_name: "Rivera".to_string(),
+                first_name: "Ana".to_string(),
+                address_line: "12 Oak St*Apt 4".to_string(),
+                city: "Mobile".to_string(),
+                state_code: "AL".to_string(),
+                postal_code: "36602".to_string(),
+                birth_date: NaiveDate::from_ymd_opt(1979, 11, 23).unwrap(),
+                gender: Gender::Female,
+            },
+            prescriber: PdmpPrescriber {
+                npi: "1987654325".to_string(),
+                dea_number: "BJ1234563".to_string(),
+                last_name: "Okafor".to_string(),
+                first_name: "Chidi".to_string(),
+            },
+        }
+    }
+
+    /// A Schedule II fill, a Schedule IV refill paid by Medicaid and a
+    /// non-controlled fill, which Alabama does not collect
+    fn batch() -> Vec<Dispensation> {
+        let mut refill = dispensation("RX700113", 1, Some(DeaSchedule::IV));
+        refill.ndc = "00093-0832-01".to_string();
+        refill.quantity = 7.5;
+        refill.origin = PrescriptionOrigin::Fax;
+        refill.payment_type = PdmpPaymentType::Medicaid;
+        refill.dispensed_at = Utc.with_ymd_and_hms(2024, 3, 4, 9, 5, 0).unwrap();
+        vec![
+            dispensation("RX700112", 0, Some(DeaSchedule::II)),
+            refill,
+            dispensation("RX700114", 0, None),
+        ]
+    }
+
+    fn generated_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 5, 2, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_golden_batch() {
+        let generator = PdmpReportGenerator::new(UsStateRegistry::new());
+        let file = generator.generate(&pharmacy("AL"), &batch(), "000000042", generated_at()).unwrap();
+
+        assert_eq!(file.content, GOLDEN_BATCH.trim_end_matches('\n'));
+        assert_eq!(file.file_name, "AL_0123456_20240305020000.dat");
+        assert_eq!(file.record_count, 2);
+        assert_eq!(file.record_keys, vec!["RX700112:0", "RX700113:1"]);
+        // Alabama wants dispensations within 24 hours; the oldest sets the deadline
+        assert_eq!(file.due_by, Some(Utc.with_ymd_and_hms(2024, 3, 5, 9, 5, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_generate_rejections() {
+        let generator = PdmpReportGenerator::new(UsStateRegistry::new());
+        assert!(matches!(
+            generator.generate(&pharmacy("VI"), &batch(), "1", generated_at()),
+            Err(HimsError::ConfigurationError { .. })
+        ));
+        for control_number in ["", "1234567890", "12a"] {
+            assert!(matches!(
+                generator.generate(&pharmacy("AL"), &batch(), control_number, generated_at()),
+                Err(HimsError::ValidationError { .. })
+            ));
+        }
+
+        // Nothing reportable still makes a valid, empty file
+        let file = generator.generate(&pharmacy("AL"), &batch()[2..], "7", generated_at()).unwrap();
+        assert_eq!(file.record_count, 0);
+        assert_eq!(file.due_by, None);
+        assert!(file.content.ends_with("TP*2~\nTT*7*5~"));
+    }
+
+    #[test]
+    fn test_submission_tracking() {
+        let generator = PdmpReportGenerator::new(UsStateRegistry::new());
+        let dispensations = batch();
+        let file = generator.generate(&pharmacy("AL"), &dispensations, "42", generated_at()).unwrap();
+        let mut tracker = PdmpSubmissionTracker::new();
+        tracker.record_generated(&file);
+
+        // Generated is not reported; once the window closes both are overdue
+        let before_deadline = Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        let after_deadline = Utc.with_ymd_and_hms(2024, 3, 5, 16, 0, 0).unwrap();
+        assert!(tracker.overdue(&generator, "AL", &dispensations[..2], before_deadline).unwrap().is_empty());
+        assert_eq!(tracker.overdue(&generator, "AL", &dispensations[..2], after_deadline).unwrap().len(), 2);
+
+        tracker.mark_submitted("42", before_deadline).unwrap();
+        assert!(tracker.get_submission("42").unwrap().on_time());
+        assert!(tracker.is_reported(&dispensations[0]));
+        assert!(!tracker.is_reported(&dispensations[2]));
+        assert!(tracker.overdue(&generator, "AL", &dispensations[..2], after_deadline).unwrap().is_empty());
+
+        // A rejected file's records are unreported again
+        tracker.mark_rejected("42", after_deadline, "PAT07 missing").unwrap();
+        assert!(!tracker.get_submission("42").unwrap().on_time());
+        assert_eq!(tracker.overdue(&generator, "AL", &dispensations[..2], after_deadline).unwrap().len(), 2);
+
+        // The corrected resubmission is accepted, but late
+        let resubmitted = generator.generate(&pharmacy("AL"), &dispensations, "43", after_deadline).unwrap();
+        tracker.record_generated(&resubmitted);
+        tracker.mark_accepted("43", after_deadline).unwrap();
+        assert!(!tracker.get_submission("43").unwrap().on_time());
+        assert!(tracker.overdue(&generator, "AL", &dispensations[..2], after_deadline).unwrap().is_empty());
+
+        let listed: Vec<&str> = tracker.list_submissions().iter().map(|s| s.control_number.as_str()).collect();
+        assert_eq!(listed, vec!["42", "43"]);
+        assert!(tracker.mark_submitted("44", after_deadline).is_err());
+    }
+
+    #[test]
+    fn test_reporting_deadline() {
+        let generator = PdmpReportGenerator::new(UsStateRegistry::new());
+        let dispensed_at = Utc.with_ymd_and_hms(2024, 3, 4, 15, 30, 0).unwrap();
+        assert_eq!(generator.reporting_deadline("TX", dispensed_at).unwrap(), dispensed_at + Duration::hours(72));
+        assert_eq!(generator.reporting_deadline("AL", dispensed_at).unwrap(), dispensed_at + Duration::hours(24));
+        assert!(generator.reporting_deadline("ZZ", dispensed_at).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+// Tables of rows as CSV and as XLSX workbooks
+
+/// A value of a table
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+}
+
+impl Cell {
+    /// The value as it reads in a text file
+    pub fn display(&self) -> String {
+        match self {
+            Cell::Empty => String::new(),
+            Cell::Text(text) => text.clone(),
+            Cell::Number(number) => number.to_string(),
+        }
+    }
+}
+
+/// Named columns and rows of values, one per column
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    /// The table as CSV (RFC 4180): a header line, then a line per row,
+    /// ended by CRLF. Text a spreadsheet would take for a formula is
+    /// prefixed with `'`, so opening the file never evaluates it.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        let header: Vec<String> = self.columns.iter().map(|column| csv_field(column)).collect();
+        csv.push_str(&header.join(","));
+        csv.push_str("\r\n");
+        for row in &self.rows {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Text(text) if text.starts_with(['=', '+', '-', '@', '\t', '\r']) => {
+                        csv_field(&format!("'{}", text))
+                    }
+                    cell => csv_field(&cell.display()),
+                })
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+
+    /// The table as an Office Open XML workbook of one sheet, the columns'
+    /// names in its first row. Numbers are stored as numbers and text
+    /// inline, so the workbook needs no shared strings or styles.
+    pub fn to_xlsx(&self, sheet_name: &str) -> Vec<u8> {
+        let mut sheet = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\"><sheetData>",
+        );
+        let header: Vec<Cell> = self.columns.iter().map(|column| Cell::Text(column.clone())).collect();
+        for (index, row) in std::iter::once(&header).chain(&self.rows).enumerate() {
+            sheet.push_str(&format!("<row r=\"{}\">", index + 1));
+            for (column, cell) in row.iter().enumerate() {
+                let reference = format!("{}{}", column_letters(column), index + 1);
+                match cell {
+                    Cell::Empty => {}
+                    Cell::Number(number) if number.is_finite() => {
+                        sheet.push_str(&format!("<c r=\"{}\"><v>{}</v></c>", reference, number));
+                    }
+                    cell => sheet.push_str(&format!(
+                        "<c r=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                        reference,
+                        xml_escape(&cell.display())
+                    )),
+                }
+            }
+            sheet.push_str("</row>");
+        }
+        sheet.push_str("</sheetData></worksheet>");
+
+        let workbook = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+             xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
+             <sheets><sheet name=\"{}\" sheetId=\"1\" r:id=\"rId1\"/></sheets></workbook>",
+            xml_escape(&sheet_title(sheet_name))
+        );
+        let parts: [(&str, &[u8]); 5] = [
+            ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+            ("_rels/.rels", ROOT_RELATIONSHIPS.as_bytes()),
+            ("xl/workbook.xml", workbook.as_bytes()),
+            ("xl/_rels/workbook.xml.rels", WORKBOOK_RELATIONSHIPS.as_bytes()),
+            ("xl/worksheets/sheet1.xml", sheet.as_bytes()),
+        ];
+        zip_archive(&parts)
+    }
+}
+
+const CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+<Override PartName=\"/xl/workbook.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+<Override PartName=\"/xl/worksheets/sheet1.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>\
+</Types>";
+
+const ROOT_RELATIONSHIPS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" \
+Target=\"xl/workbook.xml\"/></Relationships>";
+
+const WORKBOOK_RELATIONSHIPS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" \
+Target=\"worksheets/sheet1.xml\"/></Relationships>";
+
+/// A CSV field, quoted if it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Letters of a zero-based column, e.g. `A`, `Z`, `AA`
+fn column_letters(mut column: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'A' + (column % 26) as u8);
+        if column < 26 {
+            break;
+        }
+        column = column / 26 - 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap_or_default()
+}
+
+/// A sheet name Excel accepts: at most 31 characters, none of `[]:*?/\`
+fn sheet_title(name: &str) -> String {
+    let title: String = name
+        .chars()
+        .map(|c| if "[]:*?/\\".contains(c) { ' ' } else { c })
+        .take(31)
+        .collect();
+    if title.trim().is_empty() {
+        "Sheet1".to_string()
+    } else {
+        title
+    }
+}
+
+/// Text for XML content or attributes, without characters XML 1.0 cannot
+/// carry
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// CRC-32 (IEEE 802.3) of bytes, as ZIP records it
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// A ZIP archive of files stored uncompressed, dated 1980-01-01
+pub fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    const DOS_DATE: u16 = 0x0021;
+
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let offset = archive.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+        // Version needed, flags, method (stored), time, date, CRC, sizes, name and extra lengths
+        let fields = |record: &mut Vec<u8>| {
+            record.extend_from_slice(&20u16.to_le_bytes());
+            record.extend_from_slice(&0u16.to_le_bytes());
+            record.extend_from_slice(&0u16.to_le_bytes());
+            record.extend_from_slice(&0u16.to_le_bytes());
+            record.extend_from_slice(&DOS_DATE.to_le_bytes());
+            record.extend_from_slice(&crc.to_le_bytes());
+            record.extend_from_slice(&size.to_le_bytes());
+            record.extend_from_slice(&size.to_le_bytes());
+            record.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            record.extend_from_slice(&0u16.to_le_bytes());
+        };
+
+        archive.extend_from_slice(&0x0403_4B50u32.to_le_bytes());
+        fields(&mut archive);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        directory.extend_from_slice(&0x0201_4B50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        fields(&mut directory);
+        // Comment length, disk, internal and external attributes, local header offset
+        directory.extend_from_slice(&0u16.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes());
+        directory.extend_from_slice(&0u32.to_le_bytes());
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&0x0605_4B50u32.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        Table {
+            columns: vec!["month".to_string(), "admissions, total".to_string()],
+            rows: vec![
+                vec![Cell::Text("2024-05".to_string()), Cell::Number(42.0)],
+                vec![Cell::Text("=HYPERLINK(\"x\")".to_string()), Cell::Empty],
+                vec![Cell::Text("a\"b".to_string()), Cell::Number(-1.5)],
+            ],
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            table().to_csv(),
+            "month,\"admissions, total\"\r\n2024-05,42\r\n\"'=HYPERLINK(\"\"x\"\")\",\r\n\"a\"\"b\",-1.5\r\n"
+        );
+    }
+
+    #[test]
+    fn test_xlsx() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(column_letters(0), "A");
+        assert_eq!(column_letters(25), "Z");
+        assert_eq!(column_letters(26), "AA");
+        assert_eq!(column_letters(701), "ZZ");
+        assert_eq!(sheet_title("Admissions: 2024/05"), "Admissions  2024 05");
+
+        let xlsx = table().to_xlsx("Admissions");
+        let contains = |needle: &[u8]| xlsx.windows(needle.len()).any(|window| window == needle);
+        assert!(xlsx.starts_with(b"PK\x03\x04"));
+        assert!(contains(b"<c r=\"B2\"><v>42</v></c>"));
+        assert!(contains(b"<t xml:space=\"preserve\">=HYPERLINK(&quot;x&quot;)</t>"));
+        assert!(contains(b"<sheet name=\"Admissions\""));
+
+        // The end of central directory record counts all five parts and
+        // points at the directory's first entry
+        let end = &xlsx[xlsx.len() - 22..];
+        assert!(end.starts_with(b"PK\x05\x06"));
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 5);
+        let directory = u32::from_le_bytes([end[16], end[17], end[18], end[19]]) as usize;
+        assert!(xlsx[directory..].starts_with(b"PK\x01\x02"));
+    }
+}
@@ -1,11 +1,850 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::core::HimsError;
+use crate::models::Gender;
+
+/// Implementation guide of the 276/277 claim status transactions
+pub const CLAIM_STATUS_VERSION: &str = "005010X212";
+
+const ELEMENT_SEPARATOR: char = '*';
+const COMPONENT_SEPARATOR: char = ':';
+const REPETITION_SEPARATOR: char = '^';
+const SEGMENT_TERMINATOR: char = '~';
+
 pub struct X12EdiExporter;
 
 impl X12EdiExporter {
     pub fn export_claim(_claim_data: &str) -> Result<String, crate::core::HimsError> {
         Ok("EDI X12 transaction".to_string()) // Placeholder
     }
-    
+
     pub fn export_eligibility_request(_request_data: &str) -> Result<String, crate::core::HimsError> {
         Ok("EDI eligibility request".to_string()) // Placeholder
     }
-}
\ No newline at end of file
+}
+
+/// Interchange and functional group addressing of outbound transactions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct X12Interchange {
+    /// ISA06, e.g. the submitter ID the clearinghouse assigned
+    pub sender_id: String,
+    /// ISA08, e.g. the clearinghouse's or payer's ID
+    pub receiver_id: String,
+    /// GS02
+    pub application_sender: String,
+    /// GS03
+    pub application_receiver: String,
+    /// Marks the interchange as test data (ISA15)
+    pub test: bool,
+}
+
+/// The provider organization asking for status (loop 2100B)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InformationReceiver {
+    pub name: String,
+    /// Electronic transmitter ID the payer or clearinghouse assigned
+    pub etin: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimPayer {
+    pub name: String,
+    /// Payer ID, e.g. from the clearinghouse's payer list
+    pub payer_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimProvider {
+    pub name: String,
+    pub npi: String,
+}
+
+/// The subscriber, who is also the patient of the claim
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimSubscriber {
+    pub member_id: String,
+    pub last_name: String,
+    pub first_name: String,
+    pub birth_date: NaiveDate,
+    pub gender: Gender,
+}
+
+/// A claim to ask a payer the status of
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimStatusInquiry {
+    /// Patient control number the claim was submitted with (CLM01); the
+    /// 277 echoes it back as the trace number
+    pub claim_id: String,
+    /// Payer's claim control number, once the payer assigned one
+    pub payer_claim_number: Option<String>,
+    pub total_charge: f64,
+    pub service_from: NaiveDate,
+    pub service_to: Option<NaiveDate>,
+    pub payer: ClaimPayer,
+    pub provider: ClaimProvider,
+    pub subscriber: ClaimSubscriber,
+}
+
+/// Generates 276 claim status inquiries
+pub struct ClaimStatusInquiryGenerator {
+    interchange: X12Interchange,
+    receiver: InformationReceiver,
+}
+
+impl ClaimStatusInquiryGenerator {
+    pub fn new(interchange: X12Interchange, receiver: InformationReceiver) -> Self {
+        Self { interchange, receiver }
+    }
+
+    /// Build an interchange with one 276 transaction asking the status of
+    /// each claim, numbered with `control_number` throughout
+    pub fn generate(
+        &self,
+        inquiries: &[ClaimStatusInquiry],
+        control_number: u32,
+        now: DateTime<Utc>,
+    ) -> Result<String, HimsError> {
+        if inquiries.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "A claim status inquiry needs at least one claim".to_string(),
+            });
+        }
+        if control_number == 0 || control_number > 999_999_999 {
+            return Err(HimsError::ValidationError {
+                message: "X12 control number must be 1-999999999".to_string(),
+            });
+        }
+        if let Some(inquiry) = inquiries.iter().find(|inquiry| inquiry.claim_id.trim().is_empty()) {
+            return Err(HimsError::ValidationError {
+                message: format!("Claim of member {} has no patient control number", inquiry.subscriber.member_id),
+            });
+        }
+
+        let interchange_control = format!("{:09}", control_number);
+        let group_control = control_number.to_string();
+        let transaction_control = format!("{:04}", control_number % 10_000);
+        let date = now.format("%Y%m%d").to_string();
+        let time = now.format("%H%M").to_string();
+
+        let mut transaction = vec![
+            segment(&["ST", "276", &transaction_control, CLAIM_STATUS_VERSION]),
+            segment(&["BHT", "0010", "13", &group_control, &date, &time]),
+        ];
+        let mut hl = 0;
+        for inquiry in inquiries {
+            // Each claim gets its own payer, receiver, provider and
+            // subscriber levels, each the parent of the next
+            let source = hl + 1;
+            transaction.push(segment(&["HL", &source.to_string(), "", "20", "1"]));
+            transaction.push(organization("PR", &inquiry.payer.name, "PI", &inquiry.payer.payer_id));
+            transaction.push(segment(&["HL", &(source + 1).to_string(), &source.to_string(), "21", "1"]));
+            transaction.push(organization("41", &self.receiver.name, "46", &self.receiver.etin));
+            transaction.push(segment(&["HL", &(source + 2).to_string(), &(source + 1).to_string(), "19", "1"]));
+            transaction.push(organization("1P", &inquiry.provider.name, "XX", &inquiry.provider.npi));
+            transaction.push(segment(&["HL", &(source + 3).to_string(), &(source + 2).to_string(), "22", "0"]));
+            transaction.extend(subscriber_segments(inquiry));
+            hl = source + 3;
+        }
+        // SE01 counts from ST through SE inclusive
+        let segment_count = transaction.len() + 1;
+        transaction.push(segment(&["SE", &segment_count.to_string(), &transaction_control]));
+
+        let usage = if self.interchange.test { "T" } else { "P" };
+        let mut segments = vec![
+            format!(
+                "ISA*00*{:10}*00*{:10}*ZZ*{:15}*ZZ*{:15}*{}*{}*{}*00501*{}*0*{}*{}{}",
+                "",
+                "",
+                truncate(&self.interchange.sender_id, 15),
+                truncate(&self.interchange.receiver_id, 15),
+                now.format("%y%m%d"),
+                time,
+                REPETITION_SEPARATOR,
+                interchange_control,
+                usage,
+                COMPONENT_SEPARATOR,
+                SEGMENT_TERMINATOR,
+            ),
+            segment(&[
+                "GS",
+                "HR",
+                &self.interchange.application_sender,
+                &self.interchange.application_receiver,
+                &date,
+                &time,
+                &group_control,
+                "X",
+                CLAIM_STATUS_VERSION,
+            ]),
+        ];
+        segments.extend(transaction);
+        segments.push(segment(&["GE", "1", &group_control]));
+        segments.push(segment(&["IEA", "1", &interchange_control]));
+        Ok(segments.join("\n"))
+    }
+}
+
+/// NM1 of an organization: its entity code, name and identifier
+fn organization(entity: &str, name: &str, qualifier: &str, id: &str) -> String {
+    segment(&["NM1", entity, "2", name, "", "", "", "", qualifier, id])
+}
+
+/// Loops 2000D to 2200D: the subscriber and the claim asked about
+fn subscriber_segments(inquiry: &ClaimStatusInquiry) -> Vec<String> {
+    let subscriber = &inquiry.subscriber;
+    let gender = match subscriber.gender {
+        Gender::Female => "F",
+        Gender::Male => "M",
+        Gender::Other | Gender::Unknown => "U",
+    };
+    let service_dates = match inquiry.service_to.filter(|to| *to != inquiry.service_from) {
+        Some(to) => ("RD8", format!("{}-{}", inquiry.service_from.format("%Y%m%d"), to.format("%Y%m%d"))),
+        None => ("D8", inquiry.service_from.format("%Y%m%d").to_string()),
+    };
+
+    let mut segments = vec![
+        segment(&["DMG", "D8", &subscriber.birth_date.format("%Y%m%d").to_string(), gender]),
+        segment(&[
+            "NM1",
+            "IL",
+            "1",
+            &subscriber.last_name,
+            &subscriber.first_name,
+            "",
+            "",
+            "",
+            "MI",
+            &subscriber.member_id,
+        ]),
+        segment(&["TRN", "1", &inquiry.claim_id]),
+    ];
+    if let Some(number) = &inquiry.payer_claim_number {
+        segments.push(segment(&["REF", "1K", number]));
+    }
+    segments.push(segment(&["REF", "EJ", &inquiry.claim_id]));
+    segments.push(segment(&["AMT", "T3", &format_amount(inquiry.total_charge)]));
+    segments.push(segment(&["DTP", "472", service_dates.0, &service_dates.1]));
+    segments
+}
+
+/// A status a payer reported: the category (e.g. `F2`, denied), the
+/// status within it and the entity it concerns
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimStatusCode {
+    pub category: String,
+    pub status: String,
+    pub entity: Option<String>,
+}
+
+/// One STC of a claim in a 277
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimStatusDetail {
+    /// The primary status (STC01), then any additional ones (STC10, STC11)
+    pub codes: Vec<ClaimStatusCode>,
+    pub effective_date: Option<NaiveDate>,
+    pub total_charge: Option<f64>,
+    pub paid_amount: Option<f64>,
+    pub adjudication_date: Option<NaiveDate>,
+    pub check_number: Option<String>,
+}
+
+impl ClaimStatusDetail {
+    /// Category of the primary status
+    pub fn category(&self) -> &str {
+        self.codes.first().map(|code| code.category.as_str()).unwrap_or("")
+    }
+}
+
+/// A payer's answer about one claim, from a 277
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimStatusResponse {
+    /// The inquiry's trace number, which is the claim's patient control
+    /// number for inquiries generated here
+    pub trace_number: String,
+    pub payer_id: Option<String>,
+    pub patient_account_number: Option<String>,
+    pub payer_claim_number: Option<String>,
+    /// Claim level statuses; service line statuses are not kept
+    pub statuses: Vec<ClaimStatusDetail>,
+}
+
+impl ClaimStatusResponse {
+    /// The patient control number the response is about
+    pub fn claim_id(&self) -> &str {
+        self.patient_account_number.as_deref().unwrap_or(&self.trace_number)
+    }
+}
+
+/// Parse the claim level statuses of a 277 claim status response
+pub fn parse_claim_status_response(content: &str) -> Result<Vec<ClaimStatusResponse>, HimsError> {
+    let content = content.trim_start();
+    // The ISA segment is fixed length: its fourth character is the element
+    // separator, its 105th the component separator and the 106th ends it.
+    // Characters rather than bytes are counted, as names may not be ASCII.
+    let isa: Vec<char> = content.chars().take(106).collect();
+    let (element, component, terminator) = match content.starts_with("ISA") && isa.len() >= 106 {
+        true => (isa[3], isa[104], isa[105]),
+        false => (ELEMENT_SEPARATOR, COMPONENT_SEPARATOR, SEGMENT_TERMINATOR),
+    };
+
+    let mut responses: Vec<ClaimStatusResponse> = Vec::new();
+    let mut is_277 = false;
+    let mut payer_id = None;
+    // Whether the segments are at claim level, rather than above any claim
+    // or in one of its service lines
+    let mut in_claim = false;
+
+    for raw in content.split(terminator) {
+        let elements: Vec<&str> = raw.trim().split(element).collect();
+        let value = |index: usize| elements.get(index).map(|v| v.trim()).filter(|v| !v.is_empty());
+        match elements[0] {
+            "ST" => is_277 = value(1) == Some("277"),
+            "HL" => in_claim = false,
+            "NM1" if value(1) == Some("PR") => payer_id = value(9).map(str::to_string),
+            "TRN" if value(1) == Some("2") => {
+                in_claim = true;
+                responses.push(ClaimStatusResponse {
+                    trace_number: value(2).unwrap_or_default().to_string(),
+                    payer_id: payer_id.clone(),
+                    patient_account_number: None,
+                    payer_claim_number: None,
+                    statuses: Vec::new(),
+                });
+            }
+            "SVC" => in_claim = false,
+            "REF" if in_claim => {
+                let response = responses.last_mut().expect("a claim is open");
+                match value(1) {
+                    Some("1K") => response.payer_claim_number = value(2).map(str::to_string),
+                    Some("EJ") => response.patient_account_number = value(2).map(str::to_string),
+                    _ => {}
+                }
+            }
+            "STC" if in_claim => {
+                let codes: Vec<ClaimStatusCode> = [1, 10, 11]
+                    .iter()
+                    .filter_map(|index| value(*index))
+                    .filter_map(|composite| status_code(composite, component))
+                    .collect();
+                if codes.is_empty() {
+                    return Err(HimsError::ValidationError {
+                        message: format!("277 status {:?} has no status code", raw.trim()),
+                    });
+                }
+                let detail = ClaimStatusDetail {
+                    codes,
+                    effective_date: value(2).and_then(parse_date),
+                    total_charge: value(4).and_then(|amount| amount.parse().ok()),
+                    paid_amount: value(5).and_then(|amount| amount.parse().ok()),
+                    adjudication_date: value(6).and_then(parse_date),
+                    check_number: value(9).map(str::to_string),
+                };
+                responses.last_mut().expect("a claim is open").statuses.push(detail);
+            }
+            _ => {}
+        }
+    }
+
+    if !is_277 {
+        return Err(HimsError::ValidationError {
+            message: "Not a 277 claim status response".to_string(),
+        });
+    }
+    Ok(responses)
+}
+
+fn status_code(composite: &str, component: char) -> Option<ClaimStatusCode> {
+    let mut parts = composite.split(component).map(str::trim);
+    let category = parts.next().filter(|c| !c.is_empty())?.to_string();
+    let status = parts.next().unwrap_or_default().to_string();
+    let entity = parts.next().filter(|e| !e.is_empty()).map(str::to_string);
+    Some(ClaimStatusCode { category, status, entity })
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y%m%d").ok()
+}
+
+/// Meaning of a claim status category code
+pub fn status_category_description(category: &str) -> &'static str {
+    match category {
+        "A0" => "Acknowledgement/Forwarded",
+        "A1" => "Acknowledgement/Receipt",
+        "A2" => "Acknowledgement/Acceptance into adjudication system",
+        "A3" => "Acknowledgement/Returned as unprocessable claim",
+        "A4" => "Acknowledgement/Not Found",
+        "A5" => "Acknowledgement/Split Claim",
+        "A6" => "Acknowledgement/Rejected for Missing Information",
+        "A7" => "Acknowledgement/Rejected for Invalid Information",
+        "A8" => "Acknowledgement/Rejected for relational field in error",
+        "F0" => "Finalized",
+        "F1" => "Finalized/Payment",
+        "F2" => "Finalized/Denial",
+        "F3" => "Finalized/Revised",
+        "F3F" => "Finalized/Forwarded",
+        "F3N" => "Finalized/Not Forwarded",
+        "F4" => "Finalized/Adjudication Complete - No payment forthcoming",
+        c if c.starts_with('P') => "Pending",
+        c if c.starts_with('R') => "Request for additional information",
+        c if c.starts_with('E') => "Response not possible",
+        "D0" => "Data Search Unsuccessful",
+        _ => "Unknown status category",
+    }
+}
+
+/// Meaning of the claim status codes billing staff most often see; others
+/// are shown by number, to be looked up in the code list
+pub fn status_code_description(status: &str) -> String {
+    let description = match status {
+        "0" => "Cannot provide further status electronically",
+        "1" => "For more detailed information, see remittance advice",
+        "2" => "More detailed information in letter",
+        "3" => "Claim has been adjudicated and is awaiting payment cycle",
+        "15" => "Requested information was not provided or was insufficient/incomplete",
+        "19" => "Entity acknowledges receipt of claim/encounter",
+        "20" => "Accepted for processing",
+        "21" => "Missing or invalid information",
+        "65" => "Claim/line has been paid",
+        "88" => "Entity not eligible for benefits for submitted dates of service",
+        "97" => "Patient eligibility not found with entity",
+        "107" => "Processed according to contract provisions",
+        _ => return format!("Claim status code {}", status),
+    };
+    description.to_string()
+}
+
+/// Where a claim is between submission and payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimStatus {
+    Submitted,
+    /// Accepted into adjudication, or pending in it
+    Accepted,
+    Paid,
+    Denied,
+    /// Returned unprocessed, e.g. for missing or invalid information
+    Rejected,
+}
+
+impl ClaimStatus {
+    /// The status a payer's claim status moves a claim to, if any. Pending
+    /// and acknowledgement statuses arriving after adjudication are stale
+    /// and ignored; a finalized claim moves again only when reprocessed.
+    pub fn after(self, detail: &ClaimStatusDetail) -> Option<ClaimStatus> {
+        let paid = detail.paid_amount.unwrap_or(0.0) > 0.0;
+        let next = match detail.category() {
+            "A0" | "A1" | "A2" | "A5" => Some(ClaimStatus::Accepted).filter(|_| self == ClaimStatus::Submitted),
+            "A3" | "A4" | "A6" | "A7" | "A8" => {
+                Some(ClaimStatus::Rejected).filter(|_| matches!(self, ClaimStatus::Submitted | ClaimStatus::Accepted))
+            }
+            c if c.starts_with('P') => Some(ClaimStatus::Accepted).filter(|_| self == ClaimStatus::Submitted),
+            "F1" => Some(ClaimStatus::Paid),
+            "F2" => Some(ClaimStatus::Denied),
+            "F0" | "F3" | "F4" if paid => Some(ClaimStatus::Paid),
+            "F0" | "F3" | "F4" => Some(ClaimStatus::Denied),
+            // Requests for information, errors in the inquiry and claims
+            // forwarded to another payer leave the claim where it is
+            _ => None,
+        };
+        next.filter(|next| *next != self && self != ClaimStatus::Rejected)
+    }
+}
+
+/// Why a payer denied or rejected a claim, for billing staff to work
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DenialReason {
+    pub category: String,
+    pub status: String,
+    pub entity: Option<String>,
+    pub description: String,
+}
+
+impl DenialReason {
+    fn from_code(code: &ClaimStatusCode) -> Self {
+        Self {
+            category: code.category.clone(),
+            status: code.status.clone(),
+            entity: code.entity.clone(),
+            description: format!(
+                "{}: {}",
+                status_category_description(&code.category),
+                status_code_description(&code.status)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimStatusChange {
+    pub claim_id: String,
+    pub from: ClaimStatus,
+    pub to: ClaimStatus,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedClaim {
+    pub claim_id: String,
+    pub payer_id: String,
+    pub total_charge: f64,
+    pub status: ClaimStatus,
+    pub submitted_at: DateTime<Utc>,
+    pub payer_claim_number: Option<String>,
+    pub paid_amount: Option<f64>,
+    /// Set while the claim is denied or rejected
+    pub denial_reasons: Vec<DenialReason>,
+    pub last_inquiry_at: Option<DateTime<Utc>>,
+    pub history: Vec<ClaimStatusChange>,
+}
+
+impl TrackedClaim {
+    fn move_to(&mut self, to: ClaimStatus, at: DateTime<Utc>) -> ClaimStatusChange {
+        let change = ClaimStatusChange {
+            claim_id: self.claim_id.clone(),
+            from: self.status,
+            to,
+            at,
+        };
+        self.status = to;
+        self.history.push(change.clone());
+        change
+    }
+}
+
+/// Tracks submitted claims through their 277 claim status responses and
+/// keeps the denials billing staff have to work
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ClaimStatusTracker {
+    claims: HashMap<String, TrackedClaim>,
+}
+
+impl ClaimStatusTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_submitted(
+        &mut self,
+        claim_id: &str,
+        payer_id: &str,
+        total_charge: f64,
+        at: DateTime<Utc>,
+    ) -> Result<(), HimsError> {
+        if self.claims.contains_key(claim_id) {
+            return Err(HimsError::ValidationError {
+                message: format!("Claim {} is already being tracked", claim_id),
+            });
+        }
+        self.claims.insert(
+            claim_id.to_string(),
+            TrackedClaim {
+                claim_id: claim_id.to_string(),
+                payer_id: payer_id.to_string(),
+                total_charge,
+                status: ClaimStatus::Submitted,
+                submitted_at: at,
+                payer_claim_number: None,
+                paid_amount: None,
+                denial_reasons: Vec::new(),
+                last_inquiry_at: None,
+                history: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// A denied or rejected claim, corrected and sent again
+    pub fn record_resubmitted(&mut self, claim_id: &str, at: DateTime<Utc>) -> Result<ClaimStatusChange, HimsError> {
+        let claim = self.claim_mut(claim_id)?;
+        if !matches!(claim.status, ClaimStatus::Denied | ClaimStatus::Rejected) {
+            return Err(HimsError::ValidationError {
+                message: format!("Claim {} is {:?} and cannot be resubmitted", claim_id, claim.status),
+            });
+        }
+        claim.denial_reasons.clear();
+        claim.paid_amount = None;
+        claim.submitted_at = at;
+        Ok(claim.move_to(ClaimStatus::Submitted, at))
+    }
+
+    /// Claims awaiting adjudication not asked about in `interval`, to
+    /// send 276 inquiries for
+    pub fn due_for_inquiry(&self, now: DateTime<Utc>, interval: chrono::Duration) -> Vec<&TrackedClaim> {
+        let mut due: Vec<&TrackedClaim> = self
+            .claims
+            .values()
+            .filter(|claim| matches!(claim.status, ClaimStatus::Submitted | ClaimStatus::Accepted))
+            .filter(|claim| claim.last_inquiry_at.unwrap_or(claim.submitted_at) + interval <= now)
+            .collect();
+        due.sort_by_key(|claim| claim.submitted_at);
+        due
+    }
+
+    pub fn mark_inquired(&mut self, claim_id: &str, at: DateTime<Utc>) -> Result<(), HimsError> {
+        self.claim_mut(claim_id)?.last_inquiry_at = Some(at);
+        Ok(())
+    }
+
+    /// Move a claim on by a payer's response, keeping why it was denied or
+    /// rejected
+    pub fn apply_response(
+        &mut self,
+        response: &ClaimStatusResponse,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<ClaimStatusChange>, HimsError> {
+        let claim = self.claim_mut(response.claim_id())?;
+        if response.payer_claim_number.is_some() {
+            claim.payer_claim_number = response.payer_claim_number.clone();
+        }
+
+        let mut changes = Vec::new();
+        for detail in &response.statuses {
+            let Some(next) = claim.status.after(detail) else {
+                continue;
+            };
+            claim.denial_reasons = match next {
+                ClaimStatus::Denied | ClaimStatus::Rejected => detail.codes.iter().map(DenialReason::from_code).collect(),
+                _ => Vec::new(),
+            };
+            if next == ClaimStatus::Paid {
+                claim.paid_amount = detail.paid_amount;
+            }
+            changes.push(claim.move_to(next, at));
+        }
+        Ok(changes)
+    }
+
+    /// Parse a 277 and apply its responses; responses about claims not
+    /// tracked here are skipped
+    pub fn apply_277(&mut self, content: &str, at: DateTime<Utc>) -> Result<Vec<ClaimStatusChange>, HimsError> {
+        let mut changes = Vec::new();
+        for response in parse_claim_status_response(content)? {
+            if !self.claims.contains_key(response.claim_id()) {
+                tracing::warn!("277 response for untracked claim {}", response.claim_id());
+                continue;
+            }
+            changes.extend(self.apply_response(&response, at)?);
+        }
+        Ok(changes)
+    }
+
+    pub fn get_claim(&self, claim_id: &str) -> Option<&TrackedClaim> {
+        self.claims.get(claim_id)
+    }
+
+    pub fn list_claims(&self) -> Vec<&TrackedClaim> {
+        let mut claims: Vec<&TrackedClaim> = self.claims.values().collect();
+        claims.sort_by(|a, b| a.claim_id.cmp(&b.claim_id));
+        claims
+    }
+
+    /// Denied and rejected claims with their reasons, oldest first: the
+    /// work queue of billing staff
+    pub fn denials(&self) -> Vec<&TrackedClaim> {
+        let mut denials: Vec<&TrackedClaim> = self
+            .claims
+            .values()
+            .filter(|claim| matches!(claim.status, ClaimStatus::Denied | ClaimStatus::Rejected))
+            .collect();
+        denials.sort_by_key(|claim| claim.submitted_at);
+        denials
+    }
+
+    fn claim_mut(&mut self, claim_id: &str) -> Result<&mut TrackedClaim, HimsError> {
+        self.claims.get_mut(claim_id).ok_or_else(|| HimsError::ValidationError {
+            message: format!("Claim {} is not being tracked", claim_id),
+        })
+    }
+}
+
+fn format_amount(amount: f64) -> String {
+    let formatted = format!("{:.2}", amount);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn truncate(value: &str, length: usize) -> String {
+    value.chars().take(length).collect()
+}
+
+/// Join elements into a segment, stripping delimiter characters from values
+/// and dropping trailing empty elements
+fn segment(elements: &[&str]) -> String {
+    let used = elements.iter().rposition(|value| !value.is_empty()).map_or(0, |last| last + 1);
+    let mut segment = String::new();
+    for (index, value) in elements[..used].iter().enumerate() {
+        if index > 0 {
+            segment.push(ELEMENT_SEPARATOR);
+        }
+        segment.extend(value.chars().filter(|c| {
+            ![ELEMENT_SEPARATOR, COMPONENT_SEPARATOR, REPETITION_SEPARATOR, SEGMENT_TERMINATOR].contains(c)
+        }));
+    }
+    segment.push(SEGMENT_TERMINATOR);
+    segment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const RESPONSE: &str = "ISA*00*          *00*          *ZZ*PAYER          *ZZ*SUBMITTER      *240315*1200*^*00501*000000002*0*P*:~
+GS*HN*PAYER*SUBMITTER*20240315*1200*2*X*005010X212~
+ST*277*0001*005010X212~
+BHT*0010*08*1*20240315*1200*DG~
+HL*1**20*1~
+NM1*PR*2*ACME HEALTH*****PI*12345~
+HL*2*1*21*1~
+NM1*41*2*CITY CLINIC*****46*ETIN1~
+HL*3*2*19*1~
+NM1*1P*2*CITY CLINIC*****XX*1234567893~
+HL*4*3*22*0~
+NM1*QC*1*DOE*JANE~
+TRN*2*CLM001~
+STC*F2:88:PR*20240314**150*0~
+REF*1K*PAYER-9~
+REF*EJ*CLM001~
+SVC*HC:99213*150*0~
+STC*F2:21*20240314**150*0~
+HL*5*3*22*0~
+NM1*QC*1*ROE*RICHARD~
+TRN*2*CLM002~
+STC*F1:65*20240314**200*180*20240313**20240315*CHK100~
+SE*19*0001~
+GE*1*2~
+IEA*1*000000002~";
+
+    fn inquiry() -> ClaimStatusInquiry {
+        ClaimStatusInquiry {
+            claim_id: "CLM001".to_string(),
+            payer_claim_number: None,
+            total_charge: 150.0,
+            service_from: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            service_to: None,
+            payer: ClaimPayer {
+                name: "ACME HEALTH".to_string(),
+                payer_id: "12345".to_string(),
+            },
+            provider: ClaimProvider {
+                name: "CITY CLINIC".to_string(),
+                npi: "1234567893".to_string(),
+            },
+            subscriber: ClaimSubscriber {
+                member_id: "MEM1".to_string(),
+                last_name: "DOE".to_string(),
+                first_name: "JANE".to_string(),
+                birth_date: NaiveDate::from_ymd_opt(1980, 2, 29).unwrap(),
+                gender: Gender::Female,
+            },
+        }
+    }
+
+    #[test]
+    fn test_generate_claim_status_inquiry() {
+        let generator = ClaimStatusInquiryGenerator::new(
+            X12Interchange {
+                sender_id: "SUBMITTER".to_string(),
+                receiver_id: "PAYER".to_string(),
+                application_sender: "SUBMITTER".to_string(),
+                application_receiver: "PAYER".to_string(),
+                test: true,
+            },
+            InformationReceiver {
+                name: "CITY CLINIC".to_string(),
+                etin: "ETIN1".to_string(),
+            },
+        );
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 9, 30, 0).unwrap();
+        let content = generator.generate(&[inquiry()], 7, now).unwrap();
+        let segments: Vec<&str> = content.lines().collect();
+
+        assert_eq!(segments[0].len(), 106);
+        assert!(segments[0].ends_with("*000000007*0*T*:~"));
+        assert!(segments.contains(&"ST*276*0007*005010X212~"));
+        assert!(segments.contains(&"HL*4*3*22*0~"));
+        assert!(segments.contains(&"DMG*D8*19800229*F~"));
+        assert!(segments.contains(&"TRN*1*CLM001~"));
+        assert!(segments.contains(&"AMT*T3*150~"));
+        assert!(segments.contains(&"DTP*472*D8*20240301~"));
+        let st = segments.iter().position(|s| s.starts_with("ST*")).unwrap();
+        let se = segments.iter().position(|s| s.starts_with("SE*")).unwrap();
+        assert_eq!(segments[se], format!("SE*{}*0007~", se - st + 1));
+
+        assert!(generator.generate(&[], 7, now).is_err());
+        assert!(generator.generate(&[inquiry()], 0, now).is_err());
+    }
+
+    #[test]
+    fn test_parse_claim_status_response() {
+        let responses = parse_claim_status_response(RESPONSE).unwrap();
+        assert_eq!(responses.len(), 2);
+
+        let denied = &responses[0];
+        assert_eq!(denied.claim_id(), "CLM001");
+        assert_eq!(denied.payer_id.as_deref(), Some("12345"));
+        assert_eq!(denied.payer_claim_number.as_deref(), Some("PAYER-9"));
+        // The service line's status is not the claim's
+        assert_eq!(denied.statuses.len(), 1);
+        assert_eq!(denied.statuses[0].codes[0].status, "88");
+        assert_eq!(denied.statuses[0].codes[0].entity.as_deref(), Some("PR"));
+
+        let paid = &responses[1];
+        assert_eq!(paid.statuses[0].paid_amount, Some(180.0));
+        assert_eq!(paid.statuses[0].check_number.as_deref(), Some("CHK100"));
+
+        assert!(parse_claim_status_response("ST*276*0001~").is_err());
+    }
+
+    #[test]
+    fn test_parse_claim_status_response_non_ascii_header() {
+        // Same characters, more bytes: the separators are still found
+        let accented = RESPONSE.replacen("PAYER          ", "PAYÉR          ", 1);
+        assert!(accented.len() > RESPONSE.len());
+        assert_eq!(parse_claim_status_response(&accented).unwrap().len(), 2);
+
+        // A truncated header over 106 bytes but under 106 characters
+        let truncated = format!("ISA*00*{}", "É".repeat(60));
+        assert!(truncated.len() >= 106 && truncated.chars().count() < 106);
+        assert!(parse_claim_status_response(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_claim_lifecycle() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let mut tracker = ClaimStatusTracker::new();
+        tracker.record_submitted("CLM001", "12345", 150.0, at).unwrap();
+        tracker.record_submitted("CLM002", "12345", 200.0, at).unwrap();
+        assert!(tracker.record_submitted("CLM001", "12345", 150.0, at).is_err());
+        assert_eq!(tracker.due_for_inquiry(at + chrono::Duration::days(7), chrono::Duration::days(7)).len(), 2);
+
+        let acknowledged = parse_claim_status_response(
+            "ST*277*0001~HL*1**20*1~TRN*2*CLM001~STC*A2:20*20240302~HL*2**20*1~TRN*2*CLM002~STC*A1:19*20240302~",
+        )
+        .unwrap();
+        for response in &acknowledged {
+            tracker.apply_response(response, at).unwrap();
+        }
+        assert_eq!(tracker.get_claim("CLM001").unwrap().status, ClaimStatus::Accepted);
+
+        let changes = tracker.apply_277(RESPONSE, at).unwrap();
+        assert_eq!(changes.len(), 2);
+        let denied = tracker.get_claim("CLM001").unwrap();
+        assert_eq!(denied.status, ClaimStatus::Denied);
+        assert_eq!(denied.denial_reasons[0].status, "88");
+        assert!(denied.denial_reasons[0].description.contains("not eligible"));
+        let paid = tracker.get_claim("CLM002").unwrap();
+        assert_eq!(paid.status, ClaimStatus::Paid);
+        assert_eq!(paid.paid_amount, Some(180.0));
+        assert_eq!(tracker.denials().len(), 1);
+
+        // A stale pending status does not reopen a finalized claim
+        let pending = parse_claim_status_response("ST*277*0001~TRN*2*CLM002~STC*P1:20~").unwrap();
+        assert!(tracker.apply_response(&pending[0], at).unwrap().is_empty());
+
+        tracker.record_resubmitted("CLM001", at).unwrap();
+        assert!(tracker.denials().is_empty());
+        assert!(tracker.record_resubmitted("CLM002", at).is_err());
+    }
+}
@@ -1,27 +1,56 @@
+// The uniffi scaffolding included at the end of this file is generated and
+// documents its items with blank lines after the comment
+#![cfg_attr(feature = "ffi", allow(clippy::empty_line_after_doc_comments))]
+
+#[cfg(feature = "ffi")]
 use std::sync::Arc;
+#[cfg(feature = "ffi")]
 use uniffi::*;
 
-// Core modules
+// Core modules; core, standards and clinical also build for the browser
+// under the wasm feature
 pub mod core;
 pub mod standards;
+#[cfg(feature = "server")]
 pub mod security;
+#[cfg(feature = "server")]
 pub mod exporters;
+#[cfg(feature = "server")]
 pub mod countries;
+pub mod clinical;
+#[cfg(feature = "server")]
+pub mod client;
 
 // API modules for web server (NestJS-style)
 pub mod models;
+#[cfg(feature = "server")]
 pub mod modules;
+#[cfg(feature = "server")]
 pub mod database;
 
 // Utility modules
+#[cfg(feature = "server")]
 pub mod utils;
 
+// Browser bindings
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
 // Re-exports for easier access
+// `core::auth` and `utils::auth` are reachable by path only
+#[allow(ambiguous_glob_reexports)]
 pub use crate::core::*;
 pub use crate::standards::*;
+#[cfg(feature = "server")]
 pub use crate::security::*;
+#[cfg(feature = "server")]
 pub use crate::exporters::*;
+#[cfg(feature = "server")]
 pub use crate::countries::*;
+pub use crate::clinical::*;
+#[cfg(feature = "server")]
+pub use crate::client::*;
+#[cfg(feature = "server")]
 pub use crate::utils::*;
 
 /// Configuration for HIMS SDK
@@ -31,9 +60,12 @@ pub struct HimsConfig {
     pub enable_logging: bool,
     pub country_code: Option<String>,
     pub state_code: Option<String>,
+    /// Tenant requests are made for, before a login token carries it
+    pub tenant: Option<String>,
 }
 
 /// Compliance check result
+#[cfg(feature = "ffi")]
 pub struct ComplianceCheck {
     pub level: String,
     pub authority: String,
@@ -46,6 +78,12 @@ pub struct ComplianceCheck {
 pub enum HimsError {
     #[error("Authentication failed: {message}")]
     AuthenticationError { message: String },
+    #[error("Permission denied: {message}")]
+    PermissionDenied { message: String },
+    #[error("Not found: {message}")]
+    NotFound { message: String },
+    #[error("Conflict: {message}")]
+    ConflictError { message: String },
     #[error("Network error: {message}")]
     NetworkError { message: String },
     #[error("Validation error: {field}: {message}")]
@@ -56,15 +94,29 @@ pub enum HimsError {
     InternalError { message: String },
 }
 
+/// Failures inside app-implemented callbacks, such as a reader being
+/// unplugged mid-call
+#[cfg(feature = "ffi")]
+impl From<uniffi::UnexpectedUniFFICallbackError> for HimsError {
+    fn from(e: uniffi::UnexpectedUniFFICallbackError) -> Self {
+        HimsError::InternalError { message: e.reason }
+    }
+}
+
 /// Main HIMS SDK interface for React Native
+#[cfg(feature = "ffi")]
 pub struct HimsCore {
+    #[allow(dead_code)] // Held for the compliance calls still to be implemented
     inner: Arc<HimsCoreImpl>,
 }
 
+#[cfg(feature = "ffi")]
+#[allow(dead_code)]
 struct HimsCoreImpl {
     config: HimsConfig,
 }
 
+#[cfg(feature = "ffi")]
 impl HimsCore {
     pub fn new(config: HimsConfig) -> Self {
         let inner = Arc::new(HimsCoreImpl { config });
@@ -78,14 +130,30 @@ impl HimsCore {
 
     /// Get supported countries
     pub fn get_supported_countries(&self) -> Result<Vec<String>, HimsError> {
-        Ok(vec!["US".to_string(), "IN".to_string()])
+        let registry = CountryRegistry::new();
+        let mut countries: Vec<String> = registry.list_supported_countries().into_iter().cloned().collect();
+        countries.sort();
+        Ok(countries)
     }
 
     /// Get supported states for a country
     pub fn get_supported_states(&self, country_code: String) -> Result<Vec<String>, HimsError> {
         match country_code.as_str() {
-            "US" => Ok(vec!["CA".to_string(), "TX".to_string(), "FL".to_string(), "NY".to_string(), "IL".to_string()]),
-            "IN" => Ok(vec![]), // States not yet implemented
+            "US" => Ok(UsStateRegistry::new()
+                .list_states()
+                .into_iter()
+                .map(|state| state.state_code.clone())
+                .collect()),
+            "IN" => Ok(IndianStateRegistry::new()
+                .list_states()
+                .into_iter()
+                .map(|state| state.state_code.clone())
+                .collect()),
+            "CA" => Ok(CanadianProvinceRegistry::new()
+                .list_provinces()
+                .into_iter()
+                .map(|province| province.province_code.clone())
+                .collect()),
             _ => Err(HimsError::ValidationError {
                 field: "country_code".to_string(),
                 message: format!("Unsupported country: {}", country_code),
@@ -96,9 +164,9 @@ impl HimsCore {
     /// Validate compliance for a country/state and operation
     pub fn validate_compliance(
         &self,
-        country_code: String,
-        state_code: Option<String>,
-        operation: String,
+        _country_code: String,
+        _state_code: Option<String>,
+        _operation: String,
     ) -> Result<bool, HimsError> {
         // TODO: Implement actual validation logic using country modules
         Ok(true)
@@ -108,7 +176,7 @@ impl HimsCore {
     pub fn get_compliance_requirements(
         &self,
         country_code: String,
-        state_code: Option<String>,
+        _state_code: Option<String>,
     ) -> Result<Vec<ComplianceCheck>, HimsError> {
         let checks = vec![
             ComplianceCheck {
@@ -131,4 +199,5 @@ pub fn get_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
-uniffi::setup_scaffolding!();
\ No newline at end of file
+#[cfg(feature = "ffi")]
+uniffi::include_scaffolding!("hims_core_sdk");
\ No newline at end of file
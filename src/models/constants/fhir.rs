@@ -29,4 +29,111 @@ pub const PARTICIPANT_REQUIRED_VALUE_SET: &str = "http://hl7.org/fhir/ValueSet/p
 /// FHIR Extensions
 pub const PATIENT_RELIGION_EXTENSION: &str = "http://hl7.org/fhir/StructureDefinition/patient-religion";
 pub const PATIENT_BIRTHPLACE_EXTENSION: &str = "http://hl7.org/fhir/StructureDefinition/patient-birthPlace";
-pub const PATIENT_NATIONALITY_EXTENSION: &str = "http://hl7.org/fhir/StructureDefinition/patient-nationality";
\ No newline at end of file
+pub const PATIENT_NATIONALITY_EXTENSION: &str = "http://hl7.org/fhir/StructureDefinition/patient-nationality";
+/// Condition resource and the code systems its elements use
+pub const CONDITION_RESOURCE_TYPE: &str = "Condition";
+pub const CONDITION_PROFILE: &str = "http://hl7.org/fhir/StructureDefinition/Condition";
+pub const CONDITION_CLINICAL_STATUS_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/condition-clinical";
+pub const CONDITION_VERIFICATION_STATUS_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/condition-ver-status";
+pub const CONDITION_CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/condition-category";
+pub const SNOMED_CT_SYSTEM: &str = "http://snomed.info/sct";
+/// Observation resource and the vital-signs profile
+pub const OBSERVATION_RESOURCE_TYPE: &str = "Observation";
+pub const VITAL_SIGNS_PROFILE: &str = "http://hl7.org/fhir/StructureDefinition/vitalsigns";
+pub const OBSERVATION_CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/observation-category";
+pub const LOINC_SYSTEM: &str = "http://loinc.org";
+pub const UCUM_SYSTEM: &str = "http://unitsofmeasure.org";
+/// CarePlan and Task resources
+pub const CARE_PLAN_RESOURCE_TYPE: &str = "CarePlan";
+pub const CARE_PLAN_PROFILE: &str = "http://hl7.org/fhir/StructureDefinition/CarePlan";
+pub const TASK_RESOURCE_TYPE: &str = "Task";
+pub const TASK_PROFILE: &str = "http://hl7.org/fhir/StructureDefinition/Task";
+/// ServiceRequest resource, as which referrals are served, and the
+/// extensions carrying their tracking
+pub const SERVICE_REQUEST_RESOURCE_TYPE: &str = "ServiceRequest";
+pub const SERVICE_REQUEST_PROFILE: &str = "http://hl7.org/fhir/StructureDefinition/ServiceRequest";
+pub const REFERRAL_ACCEPTANCE_EXTENSION: &str = "http://open-hims.org/fhir/StructureDefinition/referral-acceptance";
+pub const REFERRAL_APPOINTMENT_EXTENSION: &str = "http://open-hims.org/fhir/StructureDefinition/referral-appointment";
+/// SNOMED CT "Patient referral", the category of every referral
+pub const PATIENT_REFERRAL_CODE: &str = "3457005";
+/// Extension carrying an imaging order's progress through the radiology
+/// workflow, and SNOMED CT "Imaging", the category of every imaging order
+pub const IMAGING_ORDER_STATUS_EXTENSION: &str = "http://open-hims.org/fhir/StructureDefinition/imaging-order-status";
+pub const IMAGING_CATEGORY_CODE: &str = "363679005";
+/// DiagnosticReport resource, as which radiology reports are served
+pub const DIAGNOSTIC_REPORT_RESOURCE_TYPE: &str = "DiagnosticReport";
+pub const DIAGNOSTIC_SERVICE_SECTION_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v2-0074";
+/// System of DICOM UIDs as FHIR identifiers, valued `urn:oid:<uid>`
+pub const DICOM_UID_SYSTEM: &str = "urn:dicom:uid";
+/// ICD-10, as diagnoses coded in it carry it
+pub const ICD_10_SYSTEM: &str = "http://hl7.org/fhir/sid/icd-10";
+/// ICD-10-CM, which diagnoses may be coded in instead of ICD-10
+pub const ICD_10_CM_SYSTEM: &str = "http://hl7.org/fhir/sid/icd-10-cm";
+/// Code system of the notifiable diseases a case report is tagged with
+pub const NOTIFIABLE_DISEASE_SYSTEM: &str = "http://open-hims.org/fhir/CodeSystem/notifiable-disease";
+/// US billing code systems: CPT, HCPCS Level II and the National Drug Code
+pub const CPT_SYSTEM: &str = "http://www.ama-assn.org/go/cpt";
+pub const HCPCS_SYSTEM: &str = "https://www.cms.gov/Medicare/Coding/HCPCSReleaseCodeSets";
+pub const NDC_SYSTEM: &str = "http://hl7.org/fhir/sid/ndc";
+/// RxNorm, the US clinical drug vocabulary
+pub const RXNORM_SYSTEM: &str = "http://www.nlm.nih.gov/research/umls/rxnorm";
+/// MeasureReport resource, as which quality measure results are served,
+/// and the code systems its elements use
+pub const MEASURE_REPORT_RESOURCE_TYPE: &str = "MeasureReport";
+pub const MEASURE_POPULATION_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/measure-population";
+pub const MEASURE_IMPROVEMENT_NOTATION_SYSTEM: &str =
+    "http://terminology.hl7.org/CodeSystem/measure-improvement-notation";
+/// CDC vaccines administered (CVX), the code system of recorded
+/// immunizations and of the built-in immunization schedules
+pub const CVX_SYSTEM: &str = "http://hl7.org/fhir/sid/cvx";
+/// Encounter resource, as which telemedicine visits are served: of the HL7
+/// ActCode "VR" (virtual) class, with the SNOMED CT "Telemedicine
+/// consultation with patient" service type
+pub const ENCOUNTER_RESOURCE_TYPE: &str = "Encounter";
+pub const ACT_CODE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+pub const VIRTUAL_ENCOUNTER_CLASS: &str = "VR";
+pub const TELEMEDICINE_CONSULTATION_CODE: &str = "448337001";
+/// Claim and Coverage resources, as which PM-JAY pre-authorization requests
+/// are sent, and the code systems their elements use
+pub const CLAIM_RESOURCE_TYPE: &str = "Claim";
+pub const COVERAGE_RESOURCE_TYPE: &str = "Coverage";
+pub const CLAIM_TYPE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/claim-type";
+pub const PROCESS_PRIORITY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/processpriority";
+/// Ayushman Bharat PM-JAY packages and identifiers, and the Health Facility
+/// Registry's facility IDs
+pub const PMJAY_PACKAGE_SYSTEM: &str = "http://open-hims.org/fhir/CodeSystem/pmjay-package";
+pub const PMJAY_BENEFICIARY_SYSTEM: &str = "http://open-hims.org/fhir/sid/pmjay-beneficiary";
+pub const PMJAY_HOSPITAL_SYSTEM: &str = "http://open-hims.org/fhir/sid/pmjay-hospital";
+pub const NHCX_PARTICIPANT_SYSTEM: &str = "http://open-hims.org/fhir/sid/nhcx-participant";
+pub const HFR_FACILITY_SYSTEM: &str = "https://facility.ndhm.gov.in";
+/// ABHA numbers, and the ABHA addresses (`name@abdm`) patients sign in to
+/// their health lockers with
+pub const ABHA_NUMBER_SYSTEM: &str = "https://healthid.ndhm.gov.in";
+pub const ABHA_ADDRESS_SYSTEM: &str = "http://open-hims.org/fhir/sid/abha-address";
+/// Identifier type codes: HL7 v2 table 0203, with the NDHM codes for ABHA
+/// numbers and addresses
+pub const IDENTIFIER_TYPE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v2-0203";
+pub const NDHM_IDENTIFIER_TYPE_SYSTEM: &str = "https://nrces.in/ndhm/fhir/r4/CodeSystem/ndhm-identifier-type-code";
+/// DocumentReference resource, as which medical records are exchanged, and
+/// the US Core category of clinical notes
+pub const DOCUMENT_REFERENCE_RESOURCE_TYPE: &str = "DocumentReference";
+pub const US_CORE_DOCUMENT_CATEGORY_SYSTEM: &str =
+    "http://hl7.org/fhir/us/core/CodeSystem/us-core-documentreference-category";
+pub const CLINICAL_NOTE_CATEGORY_CODE: &str = "clinical-note";
+/// Provenance resource, as which the history of a resource's changes is
+/// served, and the code systems its activity and agents use
+pub const PROVENANCE_RESOURCE_TYPE: &str = "Provenance";
+pub const DATA_OPERATION_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-DataOperation";
+pub const PROVENANCE_PARTICIPANT_TYPE_SYSTEM: &str =
+    "http://terminology.hl7.org/CodeSystem/provenance-participant-type";
+/// Security label (`meta.security`) of resources served with fields
+/// withheld under minimum necessary
+pub const OBSERVATION_VALUE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-ObservationValue";
+pub const REDACTED_SECURITY_CODE: &str = "REDACTED";
+/// Labels of disclosed 42 CFR Part 2 records: restricted confidentiality and
+/// the ActCode refrain policy prohibiting redisclosure without consent, with
+/// the notice that must accompany each disclosure (42 CFR 2.32)
+pub const CONFIDENTIALITY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-Confidentiality";
+pub const RESTRICTED_CONFIDENTIALITY_CODE: &str = "R";
+pub const NO_REDISCLOSURE_SECURITY_CODE: &str = "NORDSCLCD";
+pub const PART2_REDISCLOSURE_NOTICE: &str = "42 CFR part 2 prohibits unauthorized use or disclosure of these records.";
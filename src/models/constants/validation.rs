@@ -1,4 +1,4 @@
-/// Healthcare-specific validation rules
+//! Healthcare-specific validation rules
 
 /// Patient validation
 pub const MIN_PATIENT_AGE: u8 = 0;
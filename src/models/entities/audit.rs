@@ -19,6 +19,10 @@ pub struct AuditLog {
     pub timestamp: DateTime<Utc>,
     pub source_ip: Option<String>,
     pub user_agent: Option<String>,
+    /// Session the user acted in
+    pub session_id: Option<String>,
+    /// Why the records were accessed, as an HL7 PurposeOfUse code
+    pub purpose_of_use: Option<String>,
     pub details: Option<String>,
 }
 
@@ -41,6 +45,8 @@ impl AuditLog {
             timestamp: Utc::now(),
             source_ip: None,
             user_agent: None,
+            session_id: None,
+            purpose_of_use: None,
             details: None,
         }
     }
@@ -71,6 +77,12 @@ impl AuditLog {
         self
     }
 
+    pub fn with_session(mut self, session_id: Option<String>, purpose_of_use: Option<String>) -> Self {
+        self.session_id = session_id;
+        self.purpose_of_use = purpose_of_use;
+        self
+    }
+
     pub fn with_details(mut self, details: String) -> Self {
         self.details = Some(details);
         self
@@ -116,4 +128,14 @@ impl AuditLog {
         .with_user(user_id)
         .with_resource(resource_id)
     }
-}
\ No newline at end of file
+
+    pub fn compliance_violation(outcome: AuditOutcome, details: String) -> Self {
+        Self::new(
+            AuditEventType::ComplianceViolation,
+            AuditAction::Execute,
+            "System".to_string(),
+        )
+        .with_outcome(outcome)
+        .with_details(details)
+    }
+}
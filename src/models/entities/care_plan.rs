@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::types::*;
+
+/// FHIR R4 CarePlan: how a patient's care is to be delivered, as a list of
+/// planned activities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarePlan {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub status: CarePlanStatus,
+    pub intent: CarePlanIntent,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Type of plan, e.g. a chronic-care program
+    pub category: Vec<CodeableConcept>,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
+    /// User responsible for the plan
+    pub author_id: Option<Uuid>,
+    /// Conditions the plan addresses
+    pub addresses: Vec<Uuid>,
+    pub activities: Vec<CarePlanActivity>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub meta: ResourceMeta,
+}
+
+/// One planned activity of a care plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarePlanActivity {
+    /// Stable within the plan; tasks scheduled for the activity refer to
+    /// it. New activities are given one.
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    pub code: Option<CodeableConcept>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub status: CarePlanActivityStatus,
+    /// Care-team member who carries the activity out
+    pub performer_id: Option<Uuid>,
+    /// When the activity recurs; unscheduled activities get no tasks
+    pub schedule: Option<ActivitySchedule>,
+}
+
+/// Repetition of an activity, after FHIR Timing.repeat: `frequency` times
+/// every `period` `period_unit`s
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivitySchedule {
+    pub frequency: u32,
+    pub period: u32,
+    pub period_unit: ScheduleUnit,
+    /// First occurrence; the plan's start when not set
+    pub start: Option<DateTime<Utc>>,
+    /// No occurrences after this; the plan's end when not set
+    pub end: Option<DateTime<Utc>>,
+    /// Total number of occurrences, when limited
+    pub count: Option<u32>,
+}
+
+impl CarePlan {
+    pub fn new(patient_id: Uuid, intent: CarePlanIntent) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            status: CarePlanStatus::Draft,
+            intent,
+            title: None,
+            description: None,
+            category: Vec::new(),
+            period_start: None,
+            period_end: None,
+            author_id: None,
+            addresses: Vec::new(),
+            activities: Vec::new(),
+            note: None,
+            created_at: now,
+            updated_at: now,
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: now,
+                profile: vec![crate::models::constants::CARE_PLAN_PROFILE.to_string()],
+                security: Vec::new(),
+                tag: Vec::new(),
+            },
+        }
+    }
+
+    pub fn activity(&self, activity_id: Uuid) -> Option<&CarePlanActivity> {
+        self.activities.iter().find(|activity| activity.id == activity_id)
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::types::*;
+
+/// FHIR R4 Condition: a problem, diagnosis or health concern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Condition {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// Encounter the condition was recorded or diagnosed in
+    pub encounter_id: Option<Uuid>,
+    pub clinical_status: ConditionClinicalStatus,
+    pub verification_status: ConditionVerificationStatus,
+    pub category: ConditionCategory,
+    /// What the condition is, coded in SNOMED CT and optionally other systems
+    pub code: CodeableConcept,
+    pub severity: Option<CodeableConcept>,
+    pub onset: Option<DateTime<Utc>>,
+    /// When the condition resolved or went into remission
+    pub abatement: Option<DateTime<Utc>>,
+    pub recorded_date: DateTime<Utc>,
+    pub recorder: Option<Reference>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub meta: ResourceMeta,
+}
+
+impl Condition {
+    pub fn new(patient_id: Uuid, code: CodeableConcept, category: ConditionCategory) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            encounter_id: None,
+            clinical_status: ConditionClinicalStatus::Active,
+            verification_status: ConditionVerificationStatus::Confirmed,
+            category,
+            code,
+            severity: None,
+            onset: None,
+            abatement: None,
+            recorded_date: now,
+            recorder: None,
+            note: None,
+            created_at: now,
+            updated_at: now,
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: now,
+                profile: vec![crate::models::constants::CONDITION_PROFILE.to_string()],
+                security: Vec::new(),
+                tag: Vec::new(),
+            },
+        }
+    }
+
+    /// SNOMED CT concept the condition is coded with
+    pub fn snomed_code(&self) -> Option<&str> {
+        self.code
+            .coding
+            .iter()
+            .find(|coding| coding.system.as_deref() == Some(crate::models::constants::SNOMED_CT_SYSTEM))
+            .and_then(|coding| coding.code.as_deref())
+    }
+
+    /// Whether the condition belongs on the patient's active problem list:
+    /// a current problem-list item that has not been refuted or entered in
+    /// error
+    pub fn is_active_problem(&self) -> bool {
+        self.category == ConditionCategory::ProblemListItem
+            && self.clinical_status.is_active()
+            && !matches!(
+                self.verification_status,
+                ConditionVerificationStatus::Refuted | ConditionVerificationStatus::EnteredInError
+            )
+    }
+
+    /// Mark the condition resolved as of `abated_at`
+    pub fn resolve(&mut self, abated_at: DateTime<Utc>) {
+        self.clinical_status = ConditionClinicalStatus::Resolved;
+        self.abatement = Some(abated_at);
+        self.updated_at = Utc::now();
+    }
+}
@@ -1,9 +1,17 @@
+use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
 use uuid::Uuid;
 
+use crate::models::constants::{
+    CLINICAL_NOTE_CATEGORY_CODE, DOCUMENT_REFERENCE_RESOURCE_TYPE, LOINC_SYSTEM, US_CORE_DOCUMENT_CATEGORY_SYSTEM,
+};
 use crate::models::types::*;
 
+/// Language records are written in; the model does not record one per note
+const RECORD_LANGUAGE: &str = "en";
+
 /// Medical Record model for clinical documentation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MedicalRecord {
@@ -18,6 +26,12 @@ pub struct MedicalRecord {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub meta: ResourceMeta,
+    /// Note template the record was written with
+    #[serde(default)]
+    pub template_id: Option<String>,
+    /// Values of the template's fields, by field name
+    #[serde(default)]
+    pub structured_data: Option<serde_json::Value>,
 }
 
 impl MedicalRecord {
@@ -49,6 +63,8 @@ impl MedicalRecord {
                 security: Vec::new(),
                 tag: Vec::new(),
             },
+            template_id: None,
+            structured_data: None,
         }
     }
 
@@ -75,6 +91,54 @@ impl MedicalRecord {
         self.update_metadata();
     }
 
+    /// The record as a FHIR DocumentReference, for validation against a
+    /// profile pack: a clinical note typed by its LOINC document code, with
+    /// the content as a plain-text attachment
+    pub fn to_document_reference(&self) -> Value {
+        let (type_code, type_display) = self.record_type.loinc();
+        let (status, doc_status) = match self.status {
+            DocumentStatus::Preliminary => ("current", "preliminary"),
+            DocumentStatus::Final => ("current", "final"),
+            DocumentStatus::Amended => ("current", "amended"),
+            DocumentStatus::EnteredInError => ("entered-in-error", "entered-in-error"),
+        };
+        let attachment = json!({
+            "contentType": "text/plain",
+            "language": RECORD_LANGUAGE,
+            "data": general_purpose::STANDARD.encode(&self.content),
+            "title": type_display,
+            "creation": self.created_at.to_rfc3339(),
+        });
+        let context = self.encounter_id.map(|encounter_id| {
+            json!({ "encounter": [{ "reference": format!("Encounter/{}", encounter_id) }] })
+        });
+
+        fhir_object([
+            ("resourceType", json!(DOCUMENT_REFERENCE_RESOURCE_TYPE)),
+            ("id", json!(self.id.to_string())),
+            ("status", json!(status)),
+            ("docStatus", json!(doc_status)),
+            (
+                "type",
+                json!({
+                    "coding": [{ "system": LOINC_SYSTEM, "code": type_code, "display": type_display }],
+                    "text": type_display,
+                }),
+            ),
+            (
+                "category",
+                json!([{
+                    "coding": [{ "system": US_CORE_DOCUMENT_CATEGORY_SYSTEM, "code": CLINICAL_NOTE_CATEGORY_CODE }]
+                }]),
+            ),
+            ("subject", self.subject.to_fhir()),
+            ("date", json!(self.updated_at.to_rfc3339())),
+            ("author", self.author.iter().map(Reference::to_fhir).collect()),
+            ("content", json!([{ "attachment": attachment }])),
+            ("context", context.into()),
+        ])
+    }
+
     fn update_metadata(&mut self) {
         self.meta.last_updated = Utc::now();
         if let Some(version) = &self.meta.version_id {
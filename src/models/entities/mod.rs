@@ -1,11 +1,21 @@
 pub mod patient;
 pub mod appointment;
 pub mod medical_record;
+pub mod condition;
+pub mod vital_sign;
+pub mod care_plan;
+pub mod task;
+pub mod referral;
 pub mod user;
 pub mod audit;
 
 pub use patient::*;
 pub use appointment::*;
 pub use medical_record::*;
+pub use condition::*;
+pub use vital_sign::*;
+pub use care_plan::*;
+pub use task::*;
+pub use referral::*;
 pub use user::*;
 pub use audit::*;
\ No newline at end of file
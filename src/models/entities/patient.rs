@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::Utc;
+use serde_json::{json, Value};
 use uuid::Uuid;
 
+use crate::models::constants::PATIENT_RESOURCE_TYPE;
 use crate::models::types::*;
 
 /// FHIR R4 compliant Patient model
@@ -10,6 +12,10 @@ pub struct Patient {
     /// Unique identifier for the patient
     pub id: Uuid,
     
+    /// Business identifiers (MRN, national health ID, ...)
+    #[serde(default)]
+    pub identifier: Vec<Identifier>,
+    
     /// Active status of the patient record
     pub active: bool,
     
@@ -71,6 +77,7 @@ impl Patient {
     ) -> Self {
         Self {
             id: Uuid::new_v4(),
+            identifier: Vec::new(),
             active: true,
             name,
             telecom,
@@ -115,4 +122,44 @@ impl Patient {
         self.address.push(address);
         self.update_metadata();
     }
+
+    /// The patient as a FHIR Patient resource, for validation against a
+    /// profile pack. Profiles in `meta` are left out: the pack decides
+    /// which the resource must meet.
+    pub fn to_fhir(&self) -> Value {
+        fhir_object([
+            ("resourceType", json!(PATIENT_RESOURCE_TYPE)),
+            ("id", json!(self.id.to_string())),
+            ("identifier", self.identifier.iter().map(Identifier::to_fhir).collect()),
+            ("active", json!(self.active)),
+            ("name", self.name.iter().map(HumanName::to_fhir).collect()),
+            ("telecom", self.telecom.iter().map(ContactPoint::to_fhir).collect()),
+            ("gender", json!(self.gender)),
+            ("birthDate", json!(self.birth_date)),
+            ("deceasedBoolean", json!(self.deceased)),
+            ("address", self.address.iter().map(Address::to_fhir).collect()),
+            ("maritalStatus", self.marital_status.as_ref().map(CodeableConcept::to_fhir).into()),
+            ("contact", self.contact.iter().map(PatientContact::to_fhir).collect()),
+            ("communication", self.communication.iter().map(PatientCommunication::to_fhir).collect()),
+            ("managingOrganization", self.managing_organization.as_ref().map(Reference::to_fhir).into()),
+        ])
+    }
+}
+
+impl PatientContact {
+    fn to_fhir(&self) -> Value {
+        fhir_object([
+            ("relationship", self.relationship.iter().map(CodeableConcept::to_fhir).collect()),
+            ("name", self.name.as_ref().map(HumanName::to_fhir).into()),
+            ("telecom", self.telecom.iter().map(ContactPoint::to_fhir).collect()),
+            ("address", self.address.as_ref().map(Address::to_fhir).into()),
+            ("gender", json!(self.gender)),
+        ])
+    }
+}
+
+impl PatientCommunication {
+    fn to_fhir(&self) -> Value {
+        fhir_object([("language", self.language.to_fhir()), ("preferred", json!(self.preferred))])
+    }
 }
\ No newline at end of file
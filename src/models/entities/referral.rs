@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::types::*;
+
+/// A referral of a patient to another clinician or service, served as a
+/// FHIR R4 ServiceRequest. Outbound referrals are made here; inbound ones
+/// arrive from other organizations over FHIR or HL7 v2.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Referral {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub direction: ReferralDirection,
+    pub status: ReferralStatus,
+    pub priority: TaskPriority,
+    /// The sender's number for an inbound referral
+    pub identifier: Option<String>,
+    /// What is asked for, e.g. a consultation
+    pub code: Option<CodeableConcept>,
+    /// Specialty the patient is referred to
+    pub specialty: Option<CodeableConcept>,
+    pub reason_code: Vec<CodeableConcept>,
+    /// Conditions the referral is for
+    pub reason_condition_ids: Vec<Uuid>,
+    /// User who made an outbound referral
+    pub requester_id: Option<Uuid>,
+    /// Who referred the patient, as the letter names them; the sender of an
+    /// inbound referral
+    pub requester_display: Option<String>,
+    /// User the patient is referred to, for referrals within the organization
+    pub performer_id: Option<Uuid>,
+    /// Clinician or service the patient is referred to
+    pub performer_display: Option<String>,
+    pub acceptance: ReferralAcceptance,
+    /// When the referral was accepted or declined
+    pub responded_at: Option<DateTime<Utc>>,
+    /// Why it was declined, or what the referred-to party added on accepting
+    pub response_note: Option<String>,
+    /// Appointment booked for the referral
+    pub appointment_id: Option<Uuid>,
+    pub authored_on: DateTime<Utc>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub meta: ResourceMeta,
+}
+
+impl Referral {
+    pub fn new(patient_id: Uuid, direction: ReferralDirection) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            direction,
+            status: ReferralStatus::Draft,
+            priority: TaskPriority::Routine,
+            identifier: None,
+            code: None,
+            specialty: None,
+            reason_code: Vec::new(),
+            reason_condition_ids: Vec::new(),
+            requester_id: None,
+            requester_display: None,
+            performer_id: None,
+            performer_display: None,
+            acceptance: ReferralAcceptance::Pending,
+            responded_at: None,
+            response_note: None,
+            appointment_id: None,
+            authored_on: now,
+            note: None,
+            created_at: now,
+            updated_at: now,
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: now,
+                profile: vec![crate::models::constants::SERVICE_REQUEST_PROFILE.to_string()],
+                security: Vec::new(),
+                tag: Vec::new(),
+            },
+        }
+    }
+
+    /// Who or what the patient is referred to, as a letter addresses it
+    pub fn recipient(&self) -> Option<String> {
+        self.performer_display
+            .clone()
+            .or_else(|| self.specialty.as_ref().and_then(CodeableConcept::display_text))
+    }
+}
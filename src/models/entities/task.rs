@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::types::*;
+
+/// FHIR R4 Task: a piece of work assigned to a care-team member, often one
+/// occurrence of a care plan activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// Care plan the task was scheduled from
+    pub care_plan_id: Option<Uuid>,
+    /// Activity of that care plan the task carries out
+    pub activity_id: Option<Uuid>,
+    pub status: TaskStatus,
+    pub status_reason: Option<String>,
+    pub priority: TaskPriority,
+    pub code: Option<CodeableConcept>,
+    pub description: Option<String>,
+    /// User responsible for the task
+    pub owner_id: Option<Uuid>,
+    /// User who asked for the task
+    pub requester_id: Option<Uuid>,
+    /// When the task should be completed by
+    pub due: Option<DateTime<Utc>>,
+    pub authored_on: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub meta: ResourceMeta,
+}
+
+impl Task {
+    pub fn new(patient_id: Uuid) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            care_plan_id: None,
+            activity_id: None,
+            status: TaskStatus::Requested,
+            status_reason: None,
+            priority: TaskPriority::Routine,
+            code: None,
+            description: None,
+            owner_id: None,
+            requester_id: None,
+            due: None,
+            authored_on: now,
+            last_modified: now,
+            note: None,
+            created_at: now,
+            updated_at: now,
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: now,
+                profile: vec![crate::models::constants::TASK_PROFILE.to_string()],
+                security: Vec::new(),
+                tag: Vec::new(),
+            },
+        }
+    }
+
+    /// Whether the task is past due at `now` and still open
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        !self.status.is_terminal() && self.due.is_some_and(|due| due < now)
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::types::*;
+
+/// A vital sign measurement, served as a FHIR R4 Observation conforming to
+/// the vital-signs profile for its kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VitalSign {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// Encounter the measurement was taken in
+    pub encounter_id: Option<Uuid>,
+    pub status: ObservationStatus,
+    pub kind: VitalSignKind,
+    /// The measurement, as entered; absent for blood pressure, which is
+    /// measured by its components
+    pub value: Option<Quantity>,
+    #[serde(default)]
+    pub component: Vec<VitalSignComponent>,
+    /// When the measurement was taken
+    pub effective: DateTime<Utc>,
+    /// When the measurement was recorded
+    pub issued: DateTime<Utc>,
+    /// Device that took the measurement, if not taken by hand
+    pub device: Option<DeviceSource>,
+    pub performer: Option<Reference>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub meta: ResourceMeta,
+}
+
+/// One part of a multi-part measurement, e.g. systolic pressure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VitalSignComponent {
+    pub kind: VitalSignKind,
+    pub value: Quantity,
+}
+
+/// The device a measurement came from: a reference to a registered Device,
+/// or what the capturing client knows about it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSource {
+    /// `Device/{id}` of a registered device
+    pub reference: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    /// UDI carrier as printed on the device
+    pub udi: Option<String>,
+}
+
+impl VitalSign {
+    pub fn new(patient_id: Uuid, kind: VitalSignKind, effective: DateTime<Utc>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            patient_id,
+            encounter_id: None,
+            status: ObservationStatus::Final,
+            kind,
+            value: None,
+            component: Vec::new(),
+            effective,
+            issued: now,
+            device: None,
+            performer: None,
+            note: None,
+            created_at: now,
+            updated_at: now,
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: now,
+                profile: vec![
+                    crate::models::constants::VITAL_SIGNS_PROFILE.to_string(),
+                    kind.profile().to_string(),
+                ],
+                security: Vec::new(),
+                tag: Vec::new(),
+            },
+        }
+    }
+
+    /// The component measuring `kind`
+    pub fn component(&self, kind: VitalSignKind) -> Option<&Quantity> {
+        self.component
+            .iter()
+            .find(|component| component.kind == kind)
+            .map(|component| &component.value)
+    }
+}
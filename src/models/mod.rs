@@ -3,6 +3,8 @@ pub mod constants;
 pub mod entities;
 
 // Re-export commonly used items
+// `types::fhir` and `constants::fhir` are reachable by path only
+#[allow(ambiguous_glob_reexports)]
 pub use types::*;
 pub use constants::*;
 pub use entities::*;
\ No newline at end of file
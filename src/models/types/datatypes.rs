@@ -1,4 +1,10 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::models::constants::{
+    ABHA_ADDRESS_SYSTEM, ABHA_NUMBER_SYSTEM, IDENTIFIER_TYPE_SYSTEM, NDHM_IDENTIFIER_TYPE_SYSTEM,
+};
+use crate::models::types::fhir::fhir_object;
 
 /// FHIR HumanName data type
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +40,82 @@ pub struct Address {
     pub country: Option<String>,
 }
 
+/// FHIR Identifier data type, e.g. an MRN or national health ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identifier {
+    pub use_type: Option<String>,
+    /// Namespace of the value, e.g. `https://healthid.ndhm.gov.in`
+    pub system: Option<String>,
+    pub value: String,
+}
+
+impl HumanName {
+    /// The name as FHIR JSON
+    pub fn to_fhir(&self) -> Value {
+        fhir_object([
+            ("use", json!(self.use_type)),
+            ("text", json!(self.text)),
+            ("family", json!(self.family)),
+            ("given", json!(self.given)),
+            ("prefix", json!(self.prefix)),
+            ("suffix", json!(self.suffix)),
+        ])
+    }
+}
+
+impl ContactPoint {
+    /// The contact point as FHIR JSON
+    pub fn to_fhir(&self) -> Value {
+        fhir_object([
+            ("system", json!(self.system)),
+            ("value", json!(self.value)),
+            ("use", json!(self.use_type)),
+            ("rank", json!(self.rank)),
+        ])
+    }
+}
+
+impl Address {
+    /// The address as FHIR JSON
+    pub fn to_fhir(&self) -> Value {
+        fhir_object([
+            ("use", json!(self.use_type)),
+            ("type", json!(self.address_type)),
+            ("text", json!(self.text)),
+            ("line", json!(self.line)),
+            ("city", json!(self.city)),
+            ("district", json!(self.district)),
+            ("state", json!(self.state)),
+            ("postalCode", json!(self.postal_code)),
+            ("country", json!(self.country)),
+        ])
+    }
+}
+
+impl Identifier {
+    /// Kind of identifier, as an NDHM identifier type: ABHA numbers and
+    /// addresses by their systems, and any other identifier as a medical
+    /// record number
+    fn type_code(&self) -> (&'static str, &'static str) {
+        match self.system.as_deref() {
+            Some(ABHA_NUMBER_SYSTEM) => (NDHM_IDENTIFIER_TYPE_SYSTEM, "ABHA"),
+            Some(ABHA_ADDRESS_SYSTEM) => (NDHM_IDENTIFIER_TYPE_SYSTEM, "ABHAAddress"),
+            _ => (IDENTIFIER_TYPE_SYSTEM, "MR"),
+        }
+    }
+
+    /// The identifier as FHIR JSON, typed by `type_code`
+    pub fn to_fhir(&self) -> Value {
+        let (type_system, type_code) = self.type_code();
+        fhir_object([
+            ("use", json!(self.use_type)),
+            ("type", json!({ "coding": [{ "system": type_system, "code": type_code }] })),
+            ("system", json!(self.system)),
+            ("value", json!(self.value)),
+        ])
+    }
+}
+
 // Enums for data types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NameUse {
@@ -131,6 +131,17 @@ impl MedicalRecordType {
             _ => MedicalRecordType::default(),
         }
     }
+
+    /// LOINC document type code and display
+    pub fn loinc(&self) -> (&'static str, &'static str) {
+        match self {
+            MedicalRecordType::ProgressNote => ("11506-3", "Progress note"),
+            MedicalRecordType::DischargeSummary => ("18842-5", "Discharge summary"),
+            MedicalRecordType::OperativeNote => ("11504-8", "Surgical operation note"),
+            MedicalRecordType::Consultation => ("11488-4", "Consult note"),
+            MedicalRecordType::DiagnosticReport => ("11502-2", "Laboratory report"),
+        }
+    }
 }
 
 impl DocumentStatus {
@@ -143,4 +154,707 @@ impl DocumentStatus {
             _ => DocumentStatus::default(),
         }
     }
-}
\ No newline at end of file
+}
+/// Condition clinical status (FHIR condition-clinical)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ConditionClinicalStatus {
+    #[serde(rename = "active")]
+    #[default]
+    Active,
+    #[serde(rename = "recurrence")]
+    Recurrence,
+    #[serde(rename = "relapse")]
+    Relapse,
+    #[serde(rename = "inactive")]
+    Inactive,
+    #[serde(rename = "remission")]
+    Remission,
+    #[serde(rename = "resolved")]
+    Resolved,
+}
+
+/// Condition verification status (FHIR condition-ver-status)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ConditionVerificationStatus {
+    #[serde(rename = "unconfirmed")]
+    Unconfirmed,
+    #[serde(rename = "provisional")]
+    Provisional,
+    #[serde(rename = "differential")]
+    Differential,
+    #[serde(rename = "confirmed")]
+    #[default]
+    Confirmed,
+    #[serde(rename = "refuted")]
+    Refuted,
+    #[serde(rename = "entered-in-error")]
+    EnteredInError,
+}
+
+/// Condition category (FHIR condition-category)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ConditionCategory {
+    #[serde(rename = "problem-list-item")]
+    #[default]
+    ProblemListItem,
+    #[serde(rename = "encounter-diagnosis")]
+    EncounterDiagnosis,
+}
+
+impl ConditionClinicalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConditionClinicalStatus::Active => "active",
+            ConditionClinicalStatus::Recurrence => "recurrence",
+            ConditionClinicalStatus::Relapse => "relapse",
+            ConditionClinicalStatus::Inactive => "inactive",
+            ConditionClinicalStatus::Remission => "remission",
+            ConditionClinicalStatus::Resolved => "resolved",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "recurrence" => ConditionClinicalStatus::Recurrence,
+            "relapse" => ConditionClinicalStatus::Relapse,
+            "inactive" => ConditionClinicalStatus::Inactive,
+            "remission" => ConditionClinicalStatus::Remission,
+            "resolved" => ConditionClinicalStatus::Resolved,
+            _ => ConditionClinicalStatus::Active,
+        }
+    }
+
+    /// Whether the condition is current, as opposed to abated
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            ConditionClinicalStatus::Active | ConditionClinicalStatus::Recurrence | ConditionClinicalStatus::Relapse
+        )
+    }
+}
+
+impl ConditionVerificationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConditionVerificationStatus::Unconfirmed => "unconfirmed",
+            ConditionVerificationStatus::Provisional => "provisional",
+            ConditionVerificationStatus::Differential => "differential",
+            ConditionVerificationStatus::Confirmed => "confirmed",
+            ConditionVerificationStatus::Refuted => "refuted",
+            ConditionVerificationStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "unconfirmed" => ConditionVerificationStatus::Unconfirmed,
+            "provisional" => ConditionVerificationStatus::Provisional,
+            "differential" => ConditionVerificationStatus::Differential,
+            "refuted" => ConditionVerificationStatus::Refuted,
+            "entered-in-error" => ConditionVerificationStatus::EnteredInError,
+            _ => ConditionVerificationStatus::Confirmed,
+        }
+    }
+}
+
+impl ConditionCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConditionCategory::ProblemListItem => "problem-list-item",
+            ConditionCategory::EncounterDiagnosis => "encounter-diagnosis",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "encounter-diagnosis" => ConditionCategory::EncounterDiagnosis,
+            _ => ConditionCategory::ProblemListItem,
+        }
+    }
+}
+
+/// Observation status (FHIR observation-status)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ObservationStatus {
+    #[serde(rename = "registered")]
+    Registered,
+    #[serde(rename = "preliminary")]
+    Preliminary,
+    #[serde(rename = "final")]
+    #[default]
+    Final,
+    #[serde(rename = "amended")]
+    Amended,
+    #[serde(rename = "corrected")]
+    Corrected,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "entered-in-error")]
+    EnteredInError,
+}
+
+impl ObservationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObservationStatus::Registered => "registered",
+            ObservationStatus::Preliminary => "preliminary",
+            ObservationStatus::Final => "final",
+            ObservationStatus::Amended => "amended",
+            ObservationStatus::Corrected => "corrected",
+            ObservationStatus::Cancelled => "cancelled",
+            ObservationStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "registered" => ObservationStatus::Registered,
+            "preliminary" => ObservationStatus::Preliminary,
+            "amended" => ObservationStatus::Amended,
+            "corrected" => ObservationStatus::Corrected,
+            "cancelled" => ObservationStatus::Cancelled,
+            "entered-in-error" => ObservationStatus::EnteredInError,
+            _ => ObservationStatus::Final,
+        }
+    }
+}
+
+/// A vital sign of the FHIR vital-signs profiles. Systolic and diastolic
+/// pressure are only recorded as components of blood pressure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum VitalSignKind {
+    #[serde(rename = "respiratory-rate")]
+    RespiratoryRate,
+    #[serde(rename = "heart-rate")]
+    HeartRate,
+    #[serde(rename = "oxygen-saturation")]
+    OxygenSaturation,
+    #[serde(rename = "body-temperature")]
+    BodyTemperature,
+    #[serde(rename = "body-height")]
+    BodyHeight,
+    #[serde(rename = "head-circumference")]
+    HeadCircumference,
+    #[serde(rename = "body-weight")]
+    BodyWeight,
+    #[serde(rename = "bmi")]
+    Bmi,
+    #[serde(rename = "blood-pressure")]
+    BloodPressure,
+    #[serde(rename = "systolic-blood-pressure")]
+    SystolicBloodPressure,
+    #[serde(rename = "diastolic-blood-pressure")]
+    DiastolicBloodPressure,
+}
+
+impl VitalSignKind {
+    pub const ALL: [VitalSignKind; 11] = [
+        VitalSignKind::RespiratoryRate,
+        VitalSignKind::HeartRate,
+        VitalSignKind::OxygenSaturation,
+        VitalSignKind::BodyTemperature,
+        VitalSignKind::BodyHeight,
+        VitalSignKind::HeadCircumference,
+        VitalSignKind::BodyWeight,
+        VitalSignKind::Bmi,
+        VitalSignKind::BloodPressure,
+        VitalSignKind::SystolicBloodPressure,
+        VitalSignKind::DiastolicBloodPressure,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VitalSignKind::RespiratoryRate => "respiratory-rate",
+            VitalSignKind::HeartRate => "heart-rate",
+            VitalSignKind::OxygenSaturation => "oxygen-saturation",
+            VitalSignKind::BodyTemperature => "body-temperature",
+            VitalSignKind::BodyHeight => "body-height",
+            VitalSignKind::HeadCircumference => "head-circumference",
+            VitalSignKind::BodyWeight => "body-weight",
+            VitalSignKind::Bmi => "bmi",
+            VitalSignKind::BloodPressure => "blood-pressure",
+            VitalSignKind::SystolicBloodPressure => "systolic-blood-pressure",
+            VitalSignKind::DiastolicBloodPressure => "diastolic-blood-pressure",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == s)
+    }
+
+    /// LOINC code the vital-signs profile fixes for this sign
+    pub fn loinc_code(&self) -> &'static str {
+        match self {
+            VitalSignKind::RespiratoryRate => "9279-1",
+            VitalSignKind::HeartRate => "8867-4",
+            VitalSignKind::OxygenSaturation => "2708-6",
+            VitalSignKind::BodyTemperature => "8310-5",
+            VitalSignKind::BodyHeight => "8302-2",
+            VitalSignKind::HeadCircumference => "9843-4",
+            VitalSignKind::BodyWeight => "29463-7",
+            VitalSignKind::Bmi => "39156-5",
+            VitalSignKind::BloodPressure => "85354-9",
+            VitalSignKind::SystolicBloodPressure => "8480-6",
+            VitalSignKind::DiastolicBloodPressure => "8462-4",
+        }
+    }
+
+    pub fn from_loinc_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.loinc_code() == code)
+    }
+
+    pub fn display(&self) -> &'static str {
+        match self {
+            VitalSignKind::RespiratoryRate => "Respiratory rate",
+            VitalSignKind::HeartRate => "Heart rate",
+            VitalSignKind::OxygenSaturation => "Oxygen saturation in Arterial blood",
+            VitalSignKind::BodyTemperature => "Body temperature",
+            VitalSignKind::BodyHeight => "Body height",
+            VitalSignKind::HeadCircumference => "Head Occipital-frontal circumference",
+            VitalSignKind::BodyWeight => "Body weight",
+            VitalSignKind::Bmi => "Body mass index (BMI) [Ratio]",
+            VitalSignKind::BloodPressure => "Blood pressure panel with all children optional",
+            VitalSignKind::SystolicBloodPressure => "Systolic blood pressure",
+            VitalSignKind::DiastolicBloodPressure => "Diastolic blood pressure",
+        }
+    }
+
+    /// URL of the sign's FHIR vital-signs profile
+    pub fn profile(&self) -> &'static str {
+        match self {
+            VitalSignKind::RespiratoryRate => "http://hl7.org/fhir/StructureDefinition/resprate",
+            VitalSignKind::HeartRate => "http://hl7.org/fhir/StructureDefinition/heartrate",
+            VitalSignKind::OxygenSaturation => "http://hl7.org/fhir/StructureDefinition/oxygensat",
+            VitalSignKind::BodyTemperature => "http://hl7.org/fhir/StructureDefinition/bodytemp",
+            VitalSignKind::BodyHeight => "http://hl7.org/fhir/StructureDefinition/bodyheight",
+            VitalSignKind::HeadCircumference => "http://hl7.org/fhir/StructureDefinition/headcircum",
+            VitalSignKind::BodyWeight => "http://hl7.org/fhir/StructureDefinition/bodyweight",
+            VitalSignKind::Bmi => "http://hl7.org/fhir/StructureDefinition/bmi",
+            VitalSignKind::BloodPressure
+            | VitalSignKind::SystolicBloodPressure
+            | VitalSignKind::DiastolicBloodPressure => "http://hl7.org/fhir/StructureDefinition/bp",
+        }
+    }
+
+    /// Whether the sign is only recorded as a component of another
+    pub fn is_component(&self) -> bool {
+        matches!(self, VitalSignKind::SystolicBloodPressure | VitalSignKind::DiastolicBloodPressure)
+    }
+}
+
+/// Care plan status (FHIR request-status)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CarePlanStatus {
+    #[serde(rename = "draft")]
+    #[default]
+    Draft,
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "on-hold")]
+    OnHold,
+    #[serde(rename = "revoked")]
+    Revoked,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "entered-in-error")]
+    EnteredInError,
+}
+
+impl CarePlanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CarePlanStatus::Draft => "draft",
+            CarePlanStatus::Active => "active",
+            CarePlanStatus::OnHold => "on-hold",
+            CarePlanStatus::Revoked => "revoked",
+            CarePlanStatus::Completed => "completed",
+            CarePlanStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "active" => CarePlanStatus::Active,
+            "on-hold" => CarePlanStatus::OnHold,
+            "revoked" => CarePlanStatus::Revoked,
+            "completed" => CarePlanStatus::Completed,
+            "entered-in-error" => CarePlanStatus::EnteredInError,
+            _ => CarePlanStatus::Draft,
+        }
+    }
+
+    /// Whether the plan has ended: revoked, completed or entered in error
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, CarePlanStatus::Revoked | CarePlanStatus::Completed | CarePlanStatus::EnteredInError)
+    }
+
+    /// Whether a plan in this status may move to `next`. Drafts become
+    /// active, active plans can be paused, and any plan can be marked
+    /// entered in error; ended plans stay ended.
+    pub fn can_transition_to(&self, next: CarePlanStatus) -> bool {
+        use CarePlanStatus::*;
+        *self == next
+            || next == EnteredInError
+            || matches!(
+                (self, next),
+                (Draft, Active | Revoked)
+                    | (Active, OnHold | Revoked | Completed)
+                    | (OnHold, Active | Revoked | Completed)
+            )
+    }
+}
+
+/// Care plan intent (FHIR care-plan-intent)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CarePlanIntent {
+    #[serde(rename = "proposal")]
+    Proposal,
+    #[serde(rename = "plan")]
+    #[default]
+    Plan,
+    #[serde(rename = "order")]
+    Order,
+    #[serde(rename = "option")]
+    Option,
+}
+
+impl CarePlanIntent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CarePlanIntent::Proposal => "proposal",
+            CarePlanIntent::Plan => "plan",
+            CarePlanIntent::Order => "order",
+            CarePlanIntent::Option => "option",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "proposal" => CarePlanIntent::Proposal,
+            "order" => CarePlanIntent::Order,
+            "option" => CarePlanIntent::Option,
+            _ => CarePlanIntent::Plan,
+        }
+    }
+}
+
+/// Care plan activity status (FHIR care-plan-activity-status)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CarePlanActivityStatus {
+    #[serde(rename = "not-started")]
+    #[default]
+    NotStarted,
+    #[serde(rename = "scheduled")]
+    Scheduled,
+    #[serde(rename = "in-progress")]
+    InProgress,
+    #[serde(rename = "on-hold")]
+    OnHold,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "stopped")]
+    Stopped,
+    #[serde(rename = "entered-in-error")]
+    EnteredInError,
+}
+
+impl CarePlanActivityStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CarePlanActivityStatus::NotStarted => "not-started",
+            CarePlanActivityStatus::Scheduled => "scheduled",
+            CarePlanActivityStatus::InProgress => "in-progress",
+            CarePlanActivityStatus::OnHold => "on-hold",
+            CarePlanActivityStatus::Completed => "completed",
+            CarePlanActivityStatus::Cancelled => "cancelled",
+            CarePlanActivityStatus::Stopped => "stopped",
+            CarePlanActivityStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    /// Whether tasks should still be generated for the activity
+    pub fn is_schedulable(&self) -> bool {
+        matches!(
+            self,
+            CarePlanActivityStatus::NotStarted | CarePlanActivityStatus::Scheduled | CarePlanActivityStatus::InProgress
+        )
+    }
+}
+
+/// Unit of an activity's repeat period (FHIR units-of-time)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleUnit {
+    #[serde(rename = "h")]
+    Hour,
+    #[serde(rename = "d")]
+    Day,
+    #[serde(rename = "wk")]
+    Week,
+    #[serde(rename = "mo")]
+    Month,
+}
+
+impl ScheduleUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleUnit::Hour => "h",
+            ScheduleUnit::Day => "d",
+            ScheduleUnit::Week => "wk",
+            ScheduleUnit::Month => "mo",
+        }
+    }
+}
+
+/// Task status (FHIR task-status)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum TaskStatus {
+    #[serde(rename = "draft")]
+    Draft,
+    #[serde(rename = "requested")]
+    #[default]
+    Requested,
+    #[serde(rename = "received")]
+    Received,
+    #[serde(rename = "accepted")]
+    Accepted,
+    #[serde(rename = "rejected")]
+    Rejected,
+    #[serde(rename = "ready")]
+    Ready,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "in-progress")]
+    InProgress,
+    #[serde(rename = "on-hold")]
+    OnHold,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "entered-in-error")]
+    EnteredInError,
+}
+
+impl TaskStatus {
+    pub const ALL: [TaskStatus; 12] = [
+        TaskStatus::Draft,
+        TaskStatus::Requested,
+        TaskStatus::Received,
+        TaskStatus::Accepted,
+        TaskStatus::Rejected,
+        TaskStatus::Ready,
+        TaskStatus::Cancelled,
+        TaskStatus::InProgress,
+        TaskStatus::OnHold,
+        TaskStatus::Failed,
+        TaskStatus::Completed,
+        TaskStatus::EnteredInError,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Draft => "draft",
+            TaskStatus::Requested => "requested",
+            TaskStatus::Received => "received",
+            TaskStatus::Accepted => "accepted",
+            TaskStatus::Rejected => "rejected",
+            TaskStatus::Ready => "ready",
+            TaskStatus::Cancelled => "cancelled",
+            TaskStatus::InProgress => "in-progress",
+            TaskStatus::OnHold => "on-hold",
+            TaskStatus::Failed => "failed",
+            TaskStatus::Completed => "completed",
+            TaskStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        Self::ALL.into_iter().find(|status| status.as_str() == s).unwrap_or_default()
+    }
+
+    /// Whether the task is over: nothing more will be done for it
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::Rejected
+                | TaskStatus::Cancelled
+                | TaskStatus::Failed
+                | TaskStatus::Completed
+                | TaskStatus::EnteredInError
+        )
+    }
+
+    /// Whether a task in this status may move to `next`, following the FHIR
+    /// task state machine. Any task can be marked entered in error; ended
+    /// tasks otherwise stay ended.
+    pub fn can_transition_to(&self, next: TaskStatus) -> bool {
+        use TaskStatus::*;
+        *self == next
+            || next == EnteredInError
+            || matches!(
+                (self, next),
+                (Draft, Requested | Cancelled)
+                    | (Requested, Received | Accepted | Rejected | Ready | InProgress | Cancelled)
+                    | (Received, Accepted | Rejected | Cancelled)
+                    | (Accepted, Ready | InProgress | Cancelled)
+                    | (Ready, InProgress | Completed | Failed | Cancelled)
+                    | (InProgress, OnHold | Completed | Failed | Cancelled)
+                    | (OnHold, InProgress | Failed | Cancelled)
+            )
+    }
+}
+
+/// Task priority (FHIR request-priority)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum TaskPriority {
+    #[serde(rename = "routine")]
+    #[default]
+    Routine,
+    #[serde(rename = "urgent")]
+    Urgent,
+    #[serde(rename = "asap")]
+    Asap,
+    #[serde(rename = "stat")]
+    Stat,
+}
+
+impl TaskPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskPriority::Routine => "routine",
+            TaskPriority::Urgent => "urgent",
+            TaskPriority::Asap => "asap",
+            TaskPriority::Stat => "stat",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "urgent" => TaskPriority::Urgent,
+            "asap" => TaskPriority::Asap,
+            "stat" => TaskPriority::Stat,
+            _ => TaskPriority::Routine,
+        }
+    }
+}
+
+/// Referral status (FHIR request-status of a ServiceRequest)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ReferralStatus {
+    #[serde(rename = "draft")]
+    #[default]
+    Draft,
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "on-hold")]
+    OnHold,
+    #[serde(rename = "revoked")]
+    Revoked,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "entered-in-error")]
+    EnteredInError,
+}
+
+impl ReferralStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferralStatus::Draft => "draft",
+            ReferralStatus::Active => "active",
+            ReferralStatus::OnHold => "on-hold",
+            ReferralStatus::Revoked => "revoked",
+            ReferralStatus::Completed => "completed",
+            ReferralStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "active" => ReferralStatus::Active,
+            "on-hold" => ReferralStatus::OnHold,
+            "revoked" => ReferralStatus::Revoked,
+            "completed" => ReferralStatus::Completed,
+            "entered-in-error" => ReferralStatus::EnteredInError,
+            _ => ReferralStatus::Draft,
+        }
+    }
+
+    /// Whether the referral is over: revoked, completed or entered in error
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ReferralStatus::Revoked | ReferralStatus::Completed | ReferralStatus::EnteredInError)
+    }
+
+    /// Whether a referral in this status may move to `next`. Drafts are
+    /// sent by making them active, active referrals can be paused, and any
+    /// referral can be marked entered in error; ended referrals stay ended.
+    pub fn can_transition_to(&self, next: ReferralStatus) -> bool {
+        use ReferralStatus::*;
+        *self == next
+            || next == EnteredInError
+            || matches!(
+                (self, next),
+                (Draft, Active | Revoked)
+                    | (Active, OnHold | Revoked | Completed)
+                    | (OnHold, Active | Revoked | Completed)
+            )
+    }
+}
+
+/// Whether a referral was made here or received from elsewhere
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ReferralDirection {
+    #[serde(rename = "outbound")]
+    #[default]
+    Outbound,
+    #[serde(rename = "inbound")]
+    Inbound,
+}
+
+impl ReferralDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferralDirection::Outbound => "outbound",
+            ReferralDirection::Inbound => "inbound",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "outbound" => Some(ReferralDirection::Outbound),
+            "inbound" => Some(ReferralDirection::Inbound),
+            _ => None,
+        }
+    }
+}
+
+/// The referred-to party's answer to a referral
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ReferralAcceptance {
+    #[serde(rename = "pending")]
+    #[default]
+    Pending,
+    #[serde(rename = "accepted")]
+    Accepted,
+    #[serde(rename = "declined")]
+    Declined,
+}
+
+impl ReferralAcceptance {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferralAcceptance::Pending => "pending",
+            ReferralAcceptance::Accepted => "accepted",
+            ReferralAcceptance::Declined => "declined",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "accepted" => ReferralAcceptance::Accepted,
+            "declined" => ReferralAcceptance::Declined,
+            _ => ReferralAcceptance::Pending,
+        }
+    }
+}
@@ -1,5 +1,16 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+
+/// JSON object of FHIR elements, leaving out absent ones: nulls and empty
+/// arrays, which FHIR JSON does not allow
+pub fn fhir_object<const N: usize>(elements: [(&str, Value); N]) -> Value {
+    let present = elements
+        .into_iter()
+        .filter(|(_, value)| !value.is_null() && !matches!(value, Value::Array(items) if items.is_empty()))
+        .map(|(name, value)| (name.to_string(), value));
+    Value::Object(present.collect::<Map<String, Value>>())
+}
 
 /// FHIR Reference structure for linking resources
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -8,6 +19,13 @@ pub struct Reference {
     pub display: Option<String>,
 }
 
+impl Reference {
+    /// The reference as FHIR JSON
+    pub fn to_fhir(&self) -> Value {
+        fhir_object([("reference", json!(self.reference)), ("display", json!(self.display))])
+    }
+}
+
 /// FHIR CodeableConcept for coded values
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeableConcept {
@@ -15,6 +33,25 @@ pub struct CodeableConcept {
     pub text: Option<String>,
 }
 
+impl CodeableConcept {
+    /// Text of the concept, else the display or code of its first coding
+    pub fn display_text(&self) -> Option<String> {
+        self.text.clone().or_else(|| {
+            self.coding
+                .first()
+                .and_then(|coding| coding.display.clone().or_else(|| coding.code.clone()))
+        })
+    }
+
+    /// The concept as FHIR JSON
+    pub fn to_fhir(&self) -> Value {
+        fhir_object([
+            ("coding", self.coding.iter().map(Coding::to_fhir).collect()),
+            ("text", json!(self.text)),
+        ])
+    }
+}
+
 /// FHIR Coding for individual codes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Coding {
@@ -24,6 +61,29 @@ pub struct Coding {
     pub display: Option<String>,
 }
 
+impl Coding {
+    /// The coding as FHIR JSON
+    pub fn to_fhir(&self) -> Value {
+        fhir_object([
+            ("system", json!(self.system)),
+            ("version", json!(self.version)),
+            ("code", json!(self.code)),
+            ("display", json!(self.display)),
+        ])
+    }
+}
+
+/// FHIR Quantity: a measured amount, its unit coded in `system`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quantity {
+    pub value: f64,
+    /// Unit as displayed
+    pub unit: Option<String>,
+    pub system: Option<String>,
+    /// Unit in `system`, e.g. the UCUM code `mm[Hg]`
+    pub code: Option<String>,
+}
+
 /// FHIR Resource metadata
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ResourceMeta {
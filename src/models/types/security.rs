@@ -40,6 +40,8 @@ pub enum AuditEventType {
     PatientAccess,
     #[serde(rename = "data-modification")]
     DataModification,
+    #[serde(rename = "compliance-violation")]
+    ComplianceViolation,
 }
 
 impl AuditEventType {
@@ -56,24 +58,27 @@ impl AuditEventType {
             "system-access" => Self::SystemAccess,
             "patient-access" => Self::PatientAccess,
             "data-modification" => Self::DataModification,
+            "compliance-violation" => Self::ComplianceViolation,
             _ => Self::Access, // Default fallback
         }
     }
-    
-    /// Convert to string representation  
-    pub fn to_string(&self) -> String {
-        match self {
-            Self::Create => "create".to_string(),
-            Self::Read => "read".to_string(),
-            Self::Update => "update".to_string(),
-            Self::Delete => "delete".to_string(),
-            Self::Access => "access".to_string(),
-            Self::Export => "export".to_string(),
-            Self::Authentication => "authentication".to_string(),
-            Self::SystemAccess => "system-access".to_string(),
-            Self::PatientAccess => "patient-access".to_string(),
-            Self::DataModification => "data-modification".to_string(),
-        }
+}
+
+impl std::fmt::Display for AuditEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Create => "create",
+            Self::Read => "read",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Access => "access",
+            Self::Export => "export",
+            Self::Authentication => "authentication",
+            Self::SystemAccess => "system-access",
+            Self::PatientAccess => "patient-access",
+            Self::DataModification => "data-modification",
+            Self::ComplianceViolation => "compliance-violation",
+        })
     }
 }
 
@@ -92,16 +97,15 @@ pub enum AuditAction {
     Execute,
 }
 
-impl AuditAction {
-    /// Convert to string representation  
-    pub fn to_string(&self) -> String {
-        match self {
-            Self::Create => "create".to_string(),
-            Self::Read => "read".to_string(),
-            Self::Update => "update".to_string(),
-            Self::Delete => "delete".to_string(),
-            Self::Execute => "execute".to_string(),
-        }
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Create => "create",
+            Self::Read => "read",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Execute => "execute",
+        })
     }
 }
 
@@ -118,15 +122,14 @@ pub enum AuditOutcome {
     MajorFailure,
 }
 
-impl AuditOutcome {
-    /// Convert to string representation  
-    pub fn to_string(&self) -> String {
-        match self {
-            Self::Success => "success".to_string(),
-            Self::MinorFailure => "minor-failure".to_string(),
-            Self::SeriousFailure => "serious-failure".to_string(),
-            Self::MajorFailure => "major-failure".to_string(),
-        }
+impl std::fmt::Display for AuditOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Success => "success",
+            Self::MinorFailure => "minor-failure",
+            Self::SeriousFailure => "serious-failure",
+            Self::MajorFailure => "major-failure",
+        })
     }
 }
 
@@ -157,15 +160,16 @@ impl AuditResourceType {
             _ => Self::System, // Default fallback
         }
     }
-    
-    /// Convert to string representation  
-    pub fn to_string(&self) -> String {
-        match self {
-            Self::Patient => "patient".to_string(),
-            Self::Appointment => "appointment".to_string(),
-            Self::MedicalRecord => "medical-record".to_string(),
-            Self::User => "user".to_string(),
-            Self::System => "system".to_string(),
-        }
+}
+
+impl std::fmt::Display for AuditResourceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Patient => "patient",
+            Self::Appointment => "appointment",
+            Self::MedicalRecord => "medical-record",
+            Self::User => "user",
+            Self::System => "system",
+        })
     }
-}
\ No newline at end of file
+}
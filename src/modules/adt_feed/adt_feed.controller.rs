@@ -0,0 +1,163 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::adt_feed::adt_feed_service::{Hl7Destination, Hl7DestinationRequest};
+use crate::modules::adt_feed::AdtFeedService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// ADT feed controller for HL7 destinations
+pub struct AdtFeedController {
+    adt_feed_service: Arc<AdtFeedService>,
+}
+
+impl AdtFeedController {
+    /// Create new controller with injected service
+    pub fn new(adt_feed_service: Arc<AdtFeedService>) -> Self {
+        Self { adt_feed_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/destinations", Self::create_destination, "Create HL7 destination")
+            .get("/destinations", Self::list_destinations, "List HL7 destinations")
+            .get("/destinations/:id", Self::get_destination, "Get HL7 destination by ID")
+            .put("/destinations/:id", Self::update_destination, "Update HL7 destination")
+            .delete("/destinations/:id", Self::delete_destination, "Delete HL7 destination")
+            .with_state(self.adt_feed_service.clone())
+    }
+
+    /// Create new destination
+    pub async fn create_destination(
+        State(adt_feed_service): State<Arc<AdtFeedService>>,
+        headers: HeaderMap,
+        Json(payload): Json<Hl7DestinationRequest>,
+    ) -> Result<(StatusCode, Json<Hl7Destination>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Creating HL7 destination {} at {}:{}", payload.name, payload.host, payload.port);
+
+        match adt_feed_service.create_destination(actor, payload).await {
+            Ok(destination) => Ok((StatusCode::CREATED, Json(destination))),
+            Err(e) => Err(Self::error_response("Failed to create HL7 destination", e)),
+        }
+    }
+
+    /// List destinations
+    pub async fn list_destinations(
+        State(adt_feed_service): State<Arc<AdtFeedService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<Hl7Destination>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match adt_feed_service.list_destinations().await {
+            Ok(destinations) => Ok(Json(destinations)),
+            Err(e) => Err(Self::error_response("Failed to list HL7 destinations", e)),
+        }
+    }
+
+    /// Get destination by ID
+    pub async fn get_destination(
+        State(adt_feed_service): State<Arc<AdtFeedService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Hl7Destination>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match adt_feed_service.get_destination(id).await {
+            Ok(Some(destination)) => Ok(Json(destination)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve HL7 destination", e)),
+        }
+    }
+
+    /// Update destination
+    pub async fn update_destination(
+        State(adt_feed_service): State<Arc<AdtFeedService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<Hl7DestinationRequest>,
+    ) -> Result<Json<Hl7Destination>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Updating HL7 destination: {}", id);
+
+        match adt_feed_service.update_destination(id, payload).await {
+            Ok(Some(destination)) => Ok(Json(destination)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update HL7 destination", e)),
+        }
+    }
+
+    /// Delete destination
+    pub async fn delete_destination(
+        State(adt_feed_service): State<Arc<AdtFeedService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Deleting HL7 destination: {}", id);
+
+        match adt_feed_service.delete_destination(id).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to delete HL7 destination", e)),
+        }
+    }
+
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(id: Uuid) -> (StatusCode, Json<ErrorResponse>) {
+        tracing::warn!("HL7 destination not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "HL7 destination not found".to_string(),
+                message: format!("HL7 destination with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        if status == StatusCode::FORBIDDEN {
+            tracing::warn!("{}: {}", context, e);
+        } else {
+            tracing::error!("{}: {}", context, e);
+        }
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
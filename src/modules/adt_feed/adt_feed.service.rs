@@ -0,0 +1,481 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::modules::events::{DomainEvent, EventBus, EventEnvelope};
+use crate::modules::interface_engine::{
+    Direction, HandlerError, InterfaceEngineService, MessageHandler, NewMessage, Protocol, QueuedMessage,
+};
+use crate::modules::patient::PatientService;
+use crate::standards::hl7v2::generator::{AdtEvent, AdtVisit, Hl7Generator, MessageHeader};
+use crate::standards::hl7v2::mllp::MllpClient;
+
+// Import SQL queries from separate file
+use crate::modules::adt_feed::adt_feed_sql::*;
+
+/// Attempts before a message is dead-lettered and the feed moves past it
+pub const MAX_SEND_ATTEMPTS: i32 = 10;
+
+/// A downstream system that receives the ADT feed over MLLP
+#[derive(Debug, Clone, Serialize)]
+pub struct Hl7Destination {
+    pub id: Uuid,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub sending_application: String,
+    pub sending_facility: String,
+    pub receiving_application: String,
+    pub receiving_facility: String,
+    /// Trigger events sent, e.g. `["A04", "A08"]`
+    pub events: Vec<String>,
+    pub active: bool,
+    pub next_sequence: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hl7DestinationRequest {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    #[serde(default = "Hl7DestinationRequest::default_sending_application")]
+    pub sending_application: String,
+    #[serde(default)]
+    pub sending_facility: String,
+    #[serde(default)]
+    pub receiving_application: String,
+    #[serde(default)]
+    pub receiving_facility: String,
+    #[serde(default = "Hl7DestinationRequest::default_events")]
+    pub events: Vec<String>,
+    #[serde(default = "Hl7DestinationRequest::default_active")]
+    pub active: bool,
+}
+
+impl Hl7DestinationRequest {
+    fn default_sending_application() -> String {
+        "HIMS".to_string()
+    }
+
+    fn default_events() -> Vec<String> {
+        ["A01", "A03", "A04", "A08"].iter().map(|event| event.to_string()).collect()
+    }
+
+    fn default_active() -> bool {
+        true
+    }
+}
+
+/// ADT trigger event a domain event calls for. Patient registration and
+/// demographics changes map directly; an appointment's patient arriving or
+/// being seen starts and ends the visit.
+pub fn adt_event_for(event: &DomainEvent) -> Option<AdtEvent> {
+    match event {
+        DomainEvent::PatientCreated { .. } => Some(AdtEvent::A04),
+        DomainEvent::PatientUpdated { .. } => Some(AdtEvent::A08),
+        DomainEvent::AppointmentStatusChanged { status, .. } => match status.as_str() {
+            "arrived" | "checked-in" => Some(AdtEvent::A01),
+            "fulfilled" => Some(AdtEvent::A03),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Generates ADT messages from domain events, queues them on the interface
+/// engine and sends them to each destination in order over MLLP
+pub struct AdtFeedService {
+    pool: PgPool,
+    patient_service: PatientService,
+    mllp: MllpClient,
+}
+
+impl AdtFeedService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            patient_service: PatientService::new(pool.clone()),
+            pool,
+            mllp: MllpClient::default(),
+        }
+    }
+
+    pub async fn create_destination(&self, actor: Uuid, request: Hl7DestinationRequest) -> Result<Hl7Destination, HimsError> {
+        Self::validate(&request)?;
+        let row = sqlx::query(INSERT_DESTINATION)
+            .bind(Uuid::new_v4())
+            .bind(&request.name)
+            .bind(&request.host)
+            .bind(i32::from(request.port))
+            .bind(&request.sending_application)
+            .bind(&request.sending_facility)
+            .bind(&request.receiving_application)
+            .bind(&request.receiving_facility)
+            .bind(&request.events)
+            .bind(request.active)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Self::row_to_destination(&row)
+    }
+
+    pub async fn get_destination(&self, id: Uuid) -> Result<Option<Hl7Destination>, HimsError> {
+        sqlx::query(GET_DESTINATION)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_destination)
+            .transpose()
+    }
+
+    pub async fn list_destinations(&self) -> Result<Vec<Hl7Destination>, HimsError> {
+        sqlx::query(LIST_DESTINATIONS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_destination)
+            .collect()
+    }
+
+    /// Update a destination. Deactivating it pauses its queue on the
+    /// interface engine, so queued messages wait rather than fail.
+    pub async fn update_destination(&self, id: Uuid, request: Hl7DestinationRequest) -> Result<Option<Hl7Destination>, HimsError> {
+        Self::validate(&request)?;
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let Some(row) = sqlx::query(UPDATE_DESTINATION)
+            .bind(id)
+            .bind(&request.name)
+            .bind(&request.host)
+            .bind(i32::from(request.port))
+            .bind(&request.sending_application)
+            .bind(&request.sending_facility)
+            .bind(&request.receiving_application)
+            .bind(&request.receiving_facility)
+            .bind(&request.events)
+            .bind(request.active)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db)?
+        else {
+            return Ok(None);
+        };
+        InterfaceEngineService::set_paused_by_name(&mut tx, &Self::connection(id), !request.active).await?;
+        tx.commit().await.map_err(db)?;
+        Self::row_to_destination(&row).map(Some)
+    }
+
+    /// Delete a destination along with its queued and sent messages
+    pub async fn delete_destination(&self, id: Uuid) -> Result<bool, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let result = sqlx::query(DELETE_DESTINATION).bind(id).execute(&mut *tx).await.map_err(db)?;
+        InterfaceEngineService::delete_connection_by_name(&mut tx, &Self::connection(id)).await?;
+        tx.commit().await.map_err(db)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Interface engine connection of a destination
+    pub fn connection(destination_id: Uuid) -> String {
+        format!("hl7:{}", destination_id)
+    }
+
+    fn validate(request: &Hl7DestinationRequest) -> Result<(), HimsError> {
+        let invalid = |message: String| HimsError::ValidationError { message };
+        if request.name.trim().is_empty() || request.host.trim().is_empty() {
+            return Err(invalid("Destination name and host are required".to_string()));
+        }
+        if request.port == 0 {
+            return Err(invalid("Destination port must be between 1 and 65535".to_string()));
+        }
+        if let Some(event) = request.events.iter().find(|event| AdtEvent::from_string(event).is_none()) {
+            return Err(invalid(format!("Unsupported ADT event {:?}; expected A01, A03, A04 or A08", event)));
+        }
+        Ok(())
+    }
+
+    /// Queue the ADT message an event calls for to every destination of the
+    /// event's tenant that takes it. Returns how many were queued.
+    pub async fn enqueue(&self, envelope: &EventEnvelope) -> Result<usize, HimsError> {
+        let Some(event) = adt_event_for(&envelope.event) else {
+            return Ok(0);
+        };
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        let destinations = sqlx::query(GET_DESTINATIONS_FOR_EVENT)
+            .bind(envelope.tenant_id)
+            .bind(event.as_str())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db)?
+            .iter()
+            .map(Self::row_to_destination)
+            .collect::<Result<Vec<_>, _>>()?;
+        if destinations.is_empty() {
+            return Ok(0);
+        }
+
+        let (patient_id, visit) = match &envelope.event {
+            DomainEvent::AppointmentStatusChanged { appointment_id, .. } => {
+                let Some(row) = sqlx::query(GET_APPOINTMENT_VISIT)
+                    .bind(appointment_id)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(db)?
+                else {
+                    return Ok(0);
+                };
+                let Some(patient_id) = row.get::<Option<Uuid>, _>("patient_id") else {
+                    tracing::warn!("Appointment {} has no patient; no {} sent", appointment_id, event.as_str());
+                    return Ok(0);
+                };
+                let visit = AdtVisit {
+                    patient_class: "O".to_string(),
+                    visit_number: Some(appointment_id.to_string()),
+                    location: None,
+                    admit_time: row.get("start_time"),
+                    discharge_time: if event == AdtEvent::A03 { row.get("end_time") } else { None },
+                };
+                (patient_id, Some(visit))
+            }
+            other => (other.resource().1, None),
+        };
+
+        let Some(patient) = self
+            .patient_service
+            .get_patient(patient_id)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+        else {
+            return Ok(0);
+        };
+
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        for destination in &destinations {
+            let sequence: i64 = sqlx::query_scalar(NEXT_SEQUENCE)
+                .bind(destination.id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(db)?;
+            let header = MessageHeader {
+                sending_application: destination.sending_application.clone(),
+                sending_facility: destination.sending_facility.clone(),
+                receiving_application: destination.receiving_application.clone(),
+                receiving_facility: destination.receiving_facility.clone(),
+                // Unique per destination, which is all a receiver compares
+                control_id: format!("HIMS{:016}", sequence),
+                sequence_number: Some(sequence),
+            };
+            let message =
+                Hl7Generator::generate_adt_message(event, &header, &patient, visit.as_ref(), envelope.occurred_at);
+
+            let queued = NewMessage {
+                connection: Self::connection(destination.id),
+                direction: Direction::Outbound,
+                protocol: Protocol::Hl7v2,
+                message_type: format!("ADT^{}", event.as_str()),
+                payload: message,
+                metadata: json!({
+                    "control_id": header.control_id,
+                    "sequence_number": sequence,
+                    "patient_id": patient.id,
+                }),
+                max_attempts: MAX_SEND_ATTEMPTS,
+            };
+            InterfaceEngineService::enqueue_with(&mut tx, envelope.tenant_id, &queued).await?;
+        }
+        tx.commit().await.map_err(db)?;
+        Ok(destinations.len())
+    }
+
+    /// Queue messages as events arrive, across tenants. The interface
+    /// engine sends them.
+    pub fn spawn(self: Arc<Self>, bus: &EventBus) -> JoinHandle<()> {
+        let mut events = bus.subscribe();
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            loop {
+                match events.recv().await {
+                    Ok(envelope) => {
+                        if let Err(e) = self.enqueue(&envelope).await {
+                            tracing::error!("Failed to queue ADT message for {} {}: {}", envelope.event.name(), envelope.id, e);
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::error!("ADT feed fell behind and missed {} events", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+
+    fn row_to_destination(row: &PgRow) -> Result<Hl7Destination, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        Ok(Hl7Destination {
+            id: row.try_get("id").map_err(read)?,
+            name: row.try_get("name").map_err(read)?,
+            host: row.try_get("host").map_err(read)?,
+            port: row.try_get::<i32, _>("port").map_err(read)? as u16,
+            sending_application: row.try_get("sending_application").map_err(read)?,
+            sending_facility: row.try_get("sending_facility").map_err(read)?,
+            receiving_application: row.try_get("receiving_application").map_err(read)?,
+            receiving_facility: row.try_get("receiving_facility").map_err(read)?,
+            events: row.try_get("events").map_err(read)?,
+            active: row.try_get("active").map_err(read)?,
+            next_sequence: row.try_get("next_sequence").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+            updated_at: row.try_get("updated_at").map_err(read)?,
+        })
+    }
+}
+
+#[async_trait]
+impl MessageHandler for AdtFeedService {
+    /// Send a queued message to its destination over MLLP. Accepted only
+    /// when the acknowledgement names the message's control ID.
+    async fn handle(&self, message: &QueuedMessage) -> Result<Option<String>, HandlerError> {
+        let destination_id = message
+            .connection
+            .strip_prefix("hl7:")
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(|| HandlerError::reject(format!("{} is not an HL7 destination", message.connection)))?;
+        let destination = self
+            .get_destination(destination_id)
+            .await
+            .map_err(|e| HandlerError::retry(e.to_string()))?
+            .ok_or_else(|| HandlerError::reject(format!("HL7 destination {} no longer exists", destination_id)))?;
+        let control_id = message.metadata["control_id"].as_str().unwrap_or_default();
+
+        let ack = self
+            .mllp
+            .send(&destination.host, destination.port, &message.payload)
+            .await
+            .map_err(|e| HandlerError::retry(e.to_string()))?;
+        if ack.is_accepted() && ack.control_id == control_id {
+            Ok(Some(ack.code))
+        } else if ack.is_accepted() {
+            Err(HandlerError::retry(format!("Acknowledgement names control ID {}", ack.control_id)).with_response(ack.code))
+        } else {
+            let error = ack.text.clone().unwrap_or_else(|| format!("Receiver answered {}", ack.code));
+            // A rejection (AR/CR) will not change on resending
+            let error = match ack.code.as_str() {
+                "AR" | "CR" => HandlerError::reject(error),
+                _ => HandlerError::retry(error),
+            };
+            Err(error.with_response(ack.code))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Gender, HumanName, Identifier, Patient};
+    use crate::standards::hl7v2::mllp::{frame, unframe, Acknowledgement};
+
+    fn patient() -> Patient {
+        let mut patient = Patient::new(
+            vec![HumanName {
+                use_type: None,
+                text: None,
+                family: Some("O|Brien".to_string()),
+                given: vec!["Ann".to_string(), "Marie".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            }],
+            vec![],
+            Gender::Female,
+            chrono::NaiveDate::from_ymd_opt(1980, 2, 29),
+        );
+        patient.identifier.push(Identifier {
+            use_type: None,
+            system: Some("urn:mrn".to_string()),
+            value: "MRN-7".to_string(),
+        });
+        patient
+    }
+
+    fn header() -> MessageHeader {
+        MessageHeader {
+            sending_application: "HIMS".to_string(),
+            sending_facility: "MAIN".to_string(),
+            receiving_application: "LIS".to_string(),
+            receiving_facility: "LAB".to_string(),
+            control_id: "HIMS0000000000000042".to_string(),
+            sequence_number: Some(42),
+        }
+    }
+
+    #[test]
+    fn test_events_map_to_adt_triggers() {
+        let patient_id = Uuid::new_v4();
+        assert_eq!(adt_event_for(&DomainEvent::PatientCreated { patient_id }), Some(AdtEvent::A04));
+        assert_eq!(adt_event_for(&DomainEvent::PatientUpdated { patient_id }), Some(AdtEvent::A08));
+        let status = |status: &str| DomainEvent::AppointmentStatusChanged {
+            appointment_id: Uuid::new_v4(),
+            status: status.to_string(),
+        };
+        assert_eq!(adt_event_for(&status("arrived")), Some(AdtEvent::A01));
+        assert_eq!(adt_event_for(&status("fulfilled")), Some(AdtEvent::A03));
+        assert_eq!(adt_event_for(&status("booked")), None);
+        assert_eq!(adt_event_for(&DomainEvent::PatientDeleted { patient_id }), None);
+    }
+
+    #[test]
+    fn test_adt_message_segments() {
+        let patient = patient();
+        let message = Hl7Generator::generate_adt_message(AdtEvent::A04, &header(), &patient, None, Utc::now());
+        let segments: Vec<&str> = message.trim_end_matches('\r').split('\r').collect();
+
+        assert_eq!(segments.len(), 3);
+        let msh: Vec<&str> = segments[0].split('|').collect();
+        assert_eq!(msh[8], "ADT^A04^ADT_A01");
+        assert_eq!(msh[9], "HIMS0000000000000042");
+        assert_eq!(msh[12], "42");
+
+        let pid: Vec<&str> = segments[2].split('|').collect();
+        assert_eq!(pid[3], format!("{}^^^HIMS^PI~MRN-7^^^urn:mrn", patient.id));
+        // Delimiters in data are escaped rather than splitting the field
+        assert_eq!(pid[5], "O\\F\\Brien^Ann^Marie^^");
+        assert_eq!(pid[7], "19800229");
+        assert_eq!(pid[8], "F");
+
+        let visit = AdtVisit {
+            patient_class: "O".to_string(),
+            visit_number: Some("V1".to_string()),
+            ..AdtVisit::default()
+        };
+        let message = Hl7Generator::generate_adt_message(AdtEvent::A01, &header(), &patient, Some(&visit), Utc::now());
+        let pv1: Vec<&str> = message.split('\r').nth(3).unwrap().split('|').collect();
+        assert_eq!(pv1[2], "O");
+        assert_eq!(pv1[19], "V1");
+    }
+
+    #[test]
+    fn test_mllp_framing_and_ack() {
+        let ack = "MSH|^~\\&|LIS|LAB|HIMS|MAIN|20240101||ACK^A04|1|P|2.5\rMSA|AE|HIMS42|Unknown patient class\r";
+        let framed = frame(ack);
+        assert_eq!(framed[0], 0x0b);
+        assert_eq!(unframe(&framed[..framed.len() - 1]), None);
+        let content = unframe(&framed).unwrap();
+
+        let ack = Acknowledgement::parse(std::str::from_utf8(content).unwrap()).unwrap();
+        assert!(!ack.is_accepted());
+        assert_eq!(ack.control_id, "HIMS42");
+        assert_eq!(ack.text.as_deref(), Some("Unknown patient class"));
+    }
+}
@@ -0,0 +1,62 @@
+//! ADT Feed SQL Queries
+//!
+//! This file contains all SQL queries used by the ADT feed service
+//! for clean separation of concerns and better maintainability.
+
+pub const INSERT_DESTINATION: &str = r#"
+    INSERT INTO hl7_destinations (
+        id, name, host, port, sending_application, sending_facility,
+        receiving_application, receiving_facility, events, active, created_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+    RETURNING id, name, host, port, sending_application, sending_facility,
+        receiving_application, receiving_facility, events, active, next_sequence, created_at, updated_at
+"#;
+
+pub const GET_DESTINATION: &str = r#"
+    SELECT id, name, host, port, sending_application, sending_facility,
+        receiving_application, receiving_facility, events, active, next_sequence, created_at, updated_at
+    FROM hl7_destinations
+    WHERE id = $1
+"#;
+
+pub const LIST_DESTINATIONS: &str = r#"
+    SELECT id, name, host, port, sending_application, sending_facility,
+        receiving_application, receiving_facility, events, active, next_sequence, created_at, updated_at
+    FROM hl7_destinations
+    ORDER BY name
+"#;
+
+pub const UPDATE_DESTINATION: &str = r#"
+    UPDATE hl7_destinations
+    SET name = $2, host = $3, port = $4, sending_application = $5, sending_facility = $6,
+        receiving_application = $7, receiving_facility = $8, events = $9, active = $10
+    WHERE id = $1
+    RETURNING id, name, host, port, sending_application, sending_facility,
+        receiving_application, receiving_facility, events, active, next_sequence, created_at, updated_at
+"#;
+
+pub const DELETE_DESTINATION: &str = r#"
+    DELETE FROM hl7_destinations WHERE id = $1
+"#;
+
+/// Active destinations of a tenant that take an event
+pub const GET_DESTINATIONS_FOR_EVENT: &str = r#"
+    SELECT id, name, host, port, sending_application, sending_facility,
+        receiving_application, receiving_facility, events, active, next_sequence, created_at, updated_at
+    FROM hl7_destinations
+    WHERE tenant_id = $1 AND active AND $2 = ANY(events)
+"#;
+
+/// Take the destination's next sequence number
+pub const NEXT_SEQUENCE: &str = r#"
+    UPDATE hl7_destinations SET next_sequence = next_sequence + 1
+    WHERE id = $1
+    RETURNING next_sequence - 1
+"#;
+
+/// Patient and times of an appointment, for visit events
+pub const GET_APPOINTMENT_VISIT: &str = r#"
+    SELECT patient_id, start_time, end_time
+    FROM appointments
+    WHERE id = $1
+"#;
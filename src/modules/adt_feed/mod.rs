@@ -0,0 +1,50 @@
+//! ADT Feed Module
+//!
+//! This module provides the outbound HL7 v2 ADT feed including:
+//! - A04/A08 on patient registration and update, A01/A03 when an appointment's patient arrives and is seen
+//! - Downstream destinations, each with its own trigger events and MSH-13 sequence numbers
+//! - Messages queued on the interface engine and sent over MLLP in sequence order until acknowledged
+
+#[path = "adt_feed.controller.rs"]
+pub mod adt_feed_controller;
+#[path = "adt_feed.service.rs"]
+pub mod adt_feed_service;
+#[path = "adt_feed.sql.rs"]
+pub mod adt_feed_sql;
+
+pub use adt_feed_controller::AdtFeedController;
+pub use adt_feed_service::{adt_event_for, AdtFeedService, Hl7Destination};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// ADT Feed Module Configuration
+pub struct AdtFeedModule {
+    pub service: Arc<AdtFeedService>,
+    pub controller: Arc<AdtFeedController>,
+}
+
+impl AdtFeedModule {
+    /// Create a new ADT Feed Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(AdtFeedService::new(db_pool));
+        let controller = Arc::new(AdtFeedController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register ADT feed routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<AdtFeedService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,215 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::analytics::analytics_service::{
+    AnalyticsQuery, DailyAdmissions, DailyOccupancy, DashboardSummary, DepartmentBeds, DepartmentRevenue,
+    LengthOfStay, NoShowRate, SetBedsRequest,
+};
+use crate::modules::analytics::AnalyticsService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Analytics controller for the dashboard's KPIs
+pub struct AnalyticsController {
+    analytics_service: Arc<AnalyticsService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshResponse {
+    /// False if another server was already refreshing
+    pub refreshed: bool,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl AnalyticsController {
+    /// Create new controller with injected service
+    pub fn new(analytics_service: Arc<AnalyticsService>) -> Self {
+        Self { analytics_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/summary", Self::summary, "Headline KPIs for the dashboard")
+            .get("/admissions", Self::admissions, "Daily inpatient admissions by department")
+            .get("/bed-occupancy", Self::bed_occupancy, "Daily midnight bed occupancy by department")
+            .get("/length-of-stay", Self::length_of_stay, "Average length of stay by department")
+            .get("/no-show-rate", Self::no_show_rate, "Appointment no-show rate by department")
+            .get("/revenue", Self::revenue, "Charges captured by department")
+            .get("/beds", Self::list_beds, "List the beds of each department")
+            .put("/beds/:department_id", Self::set_beds, "Set the beds of a department")
+            .post("/refresh", Self::refresh, "Recompute the analytics now")
+            .with_state(self.analytics_service.clone())
+    }
+
+    /// Headline KPIs for the dashboard
+    pub async fn summary(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+        Query(query): Query<AnalyticsQuery>,
+    ) -> Result<Json<DashboardSummary>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.summary(actor, &query).await {
+            Ok(summary) => Ok(Json(summary)),
+            Err(e) => Err(Self::error_response("Failed to summarize analytics", e)),
+        }
+    }
+
+    /// Daily inpatient admissions by department
+    pub async fn admissions(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+        Query(query): Query<AnalyticsQuery>,
+    ) -> Result<Json<Vec<DailyAdmissions>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.admissions(actor, &query).await {
+            Ok(admissions) => Ok(Json(admissions)),
+            Err(e) => Err(Self::error_response("Failed to retrieve admissions", e)),
+        }
+    }
+
+    /// Daily midnight bed occupancy by department
+    pub async fn bed_occupancy(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+        Query(query): Query<AnalyticsQuery>,
+    ) -> Result<Json<Vec<DailyOccupancy>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.bed_occupancy(actor, &query).await {
+            Ok(occupancy) => Ok(Json(occupancy)),
+            Err(e) => Err(Self::error_response("Failed to retrieve bed occupancy", e)),
+        }
+    }
+
+    /// Average length of stay by department
+    pub async fn length_of_stay(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+        Query(query): Query<AnalyticsQuery>,
+    ) -> Result<Json<Vec<LengthOfStay>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.length_of_stay(actor, &query).await {
+            Ok(stays) => Ok(Json(stays)),
+            Err(e) => Err(Self::error_response("Failed to retrieve length of stay", e)),
+        }
+    }
+
+    /// Appointment no-show rate by department
+    pub async fn no_show_rate(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+        Query(query): Query<AnalyticsQuery>,
+    ) -> Result<Json<Vec<NoShowRate>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.no_show_rate(actor, &query).await {
+            Ok(rates) => Ok(Json(rates)),
+            Err(e) => Err(Self::error_response("Failed to retrieve no-show rate", e)),
+        }
+    }
+
+    /// Charges captured by department
+    pub async fn revenue(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+        Query(query): Query<AnalyticsQuery>,
+    ) -> Result<Json<Vec<DepartmentRevenue>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.revenue(actor, &query).await {
+            Ok(revenue) => Ok(Json(revenue)),
+            Err(e) => Err(Self::error_response("Failed to retrieve revenue", e)),
+        }
+    }
+
+    /// List the beds of each department
+    pub async fn list_beds(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<DepartmentBeds>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_beds(actor).await {
+            Ok(beds) => Ok(Json(beds)),
+            Err(e) => Err(Self::error_response("Failed to list department beds", e)),
+        }
+    }
+
+    /// Set the beds of a department
+    pub async fn set_beds(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+        Path(department_id): Path<Uuid>,
+        Json(payload): Json<SetBedsRequest>,
+    ) -> Result<Json<DepartmentBeds>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.set_beds(department_id, payload.beds, actor).await {
+            Ok(beds) => Ok(Json(beds)),
+            Err(e) => Err(Self::error_response("Failed to set department beds", e)),
+        }
+    }
+
+    /// Recompute the analytics now
+    pub async fn refresh(
+        State(service): State<Arc<AnalyticsService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<RefreshResponse>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.refresh_now(actor).await {
+            Ok(refreshed) => Ok(Json(RefreshResponse { refreshed })),
+            Err(e) => Err(Self::error_response("Failed to refresh analytics", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
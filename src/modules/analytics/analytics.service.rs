@@ -0,0 +1,657 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration as Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::modules::authorization::Action;
+use crate::modules::role::RoleService;
+use crate::modules::scheduler::ScheduledJob;
+
+// Import SQL queries
+use crate::modules::analytics::analytics_sql::*;
+
+/// Days a dashboard covers unless asked otherwise
+const DEFAULT_DAYS: i64 = 30;
+
+/// Most days one request may cover
+const MAX_DAYS: i64 = 366;
+
+/// Period and department a dashboard request covers
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AnalyticsQuery {
+    /// First day, by default 30 days before `to`
+    pub from: Option<NaiveDate>,
+    /// Last day, by default today
+    pub to: Option<NaiveDate>,
+    pub department_id: Option<Uuid>,
+}
+
+impl AnalyticsQuery {
+    /// The first and last day covered, as of `today`
+    pub fn range(&self, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+        let to = self.to.unwrap_or(today);
+        let from = self.from.unwrap_or(to - Days::days(DEFAULT_DAYS - 1));
+        if from > to {
+            return Err(format!("from ({}) is after to ({})", from, to));
+        }
+        if (to - from).num_days() >= MAX_DAYS {
+            return Err(format!("A request covers at most {} days", MAX_DAYS));
+        }
+        Ok((from, to))
+    }
+}
+
+/// Departments a user's dashboards cover
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepartmentScope {
+    /// Every department, and encounters with none
+    All,
+    Departments(Vec<Uuid>),
+}
+
+impl DepartmentScope {
+    /// The departments a query is limited to, `None` for all of them, when
+    /// it asks for one department or none in particular
+    pub fn restrict(&self, department_id: Option<Uuid>) -> Result<Option<Vec<Uuid>>, String> {
+        match (self, department_id) {
+            (DepartmentScope::All, None) => Ok(None),
+            (DepartmentScope::All, Some(id)) => Ok(Some(vec![id])),
+            (DepartmentScope::Departments(ids), None) => Ok(Some(ids.clone())),
+            (DepartmentScope::Departments(ids), Some(id)) if ids.contains(&id) => Ok(Some(vec![id])),
+            (DepartmentScope::Departments(_), Some(id)) => {
+                Err(format!("Department {} is outside your departments", id))
+            }
+        }
+    }
+}
+
+/// Inpatient admissions on a day
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyAdmissions {
+    pub day: NaiveDate,
+    pub department_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub admissions: i32,
+}
+
+/// Beds occupied at midnight on a day
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyOccupancy {
+    pub day: NaiveDate,
+    pub department_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub occupied_beds: i32,
+    /// The department's beds, if they are recorded
+    pub beds: Option<i32>,
+    pub occupancy_rate: Option<f64>,
+}
+
+/// Bed occupancy of a department over a period
+#[derive(Debug, Clone, Serialize)]
+pub struct DepartmentOccupancy {
+    pub department_id: Option<Uuid>,
+    pub department: Option<String>,
+    /// Beds occupied at midnight, summed over the period's days
+    pub bed_days: i64,
+    pub beds: Option<i32>,
+    pub occupancy_rate: Option<f64>,
+}
+
+/// Average length of stay of a department's discharges
+#[derive(Debug, Clone, Serialize)]
+pub struct LengthOfStay {
+    pub department_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub discharges: i64,
+    pub patient_days: i64,
+    pub average_length_of_stay: Option<f64>,
+}
+
+/// No-show rate of a department's appointments: no-shows out of the
+/// appointments that were either attended or not
+#[derive(Debug, Clone, Serialize)]
+pub struct NoShowRate {
+    pub department_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub attended: i64,
+    pub no_shows: i64,
+    pub cancelled: i64,
+    pub no_show_rate: Option<f64>,
+}
+
+/// Charges captured for a department's encounters, in the smallest unit of
+/// the currency
+#[derive(Debug, Clone, Serialize)]
+pub struct DepartmentRevenue {
+    pub department_id: Option<Uuid>,
+    pub department: Option<String>,
+    pub charges: i64,
+    pub amount: i64,
+}
+
+/// Headline KPIs of the departments a user covers
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardSummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Departments covered, absent for all of them
+    pub departments: Option<Vec<Uuid>>,
+    pub admissions: i64,
+    /// Occupancy of the departments whose beds are recorded
+    pub occupancy_rate: Option<f64>,
+    pub discharges: i64,
+    pub average_length_of_stay: Option<f64>,
+    pub no_show_rate: Option<f64>,
+    /// Absent for users who may not view billing
+    pub revenue: Option<i64>,
+    /// When the figures were last computed
+    pub refreshed_at: Option<DateTime<Utc>>,
+}
+
+/// Beds a department has
+#[derive(Debug, Clone, Serialize)]
+pub struct DepartmentBeds {
+    pub department_id: Uuid,
+    pub department: Option<String>,
+    pub beds: i32,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetBedsRequest {
+    pub beds: i32,
+}
+
+/// `numerator / denominator` to `places` decimal places, or `None` when
+/// there is nothing to divide by
+fn rate(numerator: i64, denominator: i64, places: i32) -> Option<f64> {
+    if denominator <= 0 {
+        return None;
+    }
+    let scale = 10f64.powi(places);
+    Some((numerator as f64 / denominator as f64 * scale).round() / scale)
+}
+
+/// Roll per-department figures up into a dashboard's headline KPIs
+#[allow(clippy::too_many_arguments)]
+pub fn summarize(
+    from: NaiveDate,
+    to: NaiveDate,
+    departments: Option<Vec<Uuid>>,
+    admissions: i64,
+    occupancy: &[DepartmentOccupancy],
+    stays: &[LengthOfStay],
+    appointments: &[NoShowRate],
+    revenue: Option<&[DepartmentRevenue]>,
+) -> DashboardSummary {
+    let days = (to - from).num_days() + 1;
+    let (bed_days, capacity) = occupancy
+        .iter()
+        .filter_map(|department| department.beds.filter(|beds| *beds > 0).map(|beds| (department, beds)))
+        .fold((0, 0), |(bed_days, capacity), (department, beds)| {
+            (bed_days + department.bed_days, capacity + i64::from(beds) * days)
+        });
+    let discharges: i64 = stays.iter().map(|stay| stay.discharges).sum();
+    let patient_days: i64 = stays.iter().map(|stay| stay.patient_days).sum();
+    let attended: i64 = appointments.iter().map(|department| department.attended).sum();
+    let no_shows: i64 = appointments.iter().map(|department| department.no_shows).sum();
+
+    DashboardSummary {
+        from,
+        to,
+        departments,
+        admissions,
+        occupancy_rate: rate(bed_days, capacity, 4),
+        discharges,
+        average_length_of_stay: rate(patient_days, discharges, 2),
+        no_show_rate: rate(no_shows, attended + no_shows, 4),
+        revenue: revenue.map(|departments| departments.iter().map(|department| department.amount).sum()),
+        refreshed_at: None,
+    }
+}
+
+fn department_occupancy_from_row(row: &PgRow, days: i64) -> DepartmentOccupancy {
+    let bed_days: i64 = row.get("bed_days");
+    let beds: Option<i32> = row.get("beds");
+    DepartmentOccupancy {
+        department_id: row.get("department_id"),
+        department: row.get("department"),
+        bed_days,
+        beds,
+        occupancy_rate: beds.and_then(|beds| rate(bed_days, i64::from(beds) * days, 4)),
+    }
+}
+
+fn length_of_stay_from_row(row: &PgRow) -> LengthOfStay {
+    let discharges: i64 = row.get("discharges");
+    let patient_days: i64 = row.get("patient_days");
+    LengthOfStay {
+        department_id: row.get("department_id"),
+        department: row.get("department"),
+        discharges,
+        patient_days,
+        average_length_of_stay: rate(patient_days, discharges, 2),
+    }
+}
+
+fn no_show_rate_from_row(row: &PgRow) -> NoShowRate {
+    let attended: i64 = row.get("attended");
+    let no_shows: i64 = row.get("no_shows");
+    NoShowRate {
+        department_id: row.get("department_id"),
+        department: row.get("department"),
+        attended,
+        no_shows,
+        cancelled: row.get("cancelled"),
+        no_show_rate: rate(no_shows, attended + no_shows, 4),
+    }
+}
+
+fn department_revenue_from_row(row: &PgRow) -> DepartmentRevenue {
+    DepartmentRevenue {
+        department_id: row.get("department_id"),
+        department: row.get("department"),
+        charges: row.get("charges"),
+        amount: row.get("amount"),
+    }
+}
+
+fn department_beds_from_row(row: &PgRow) -> DepartmentBeds {
+    DepartmentBeds {
+        department_id: row.get("department_id"),
+        department: row.get("department"),
+        beds: row.get("beds"),
+        updated_by: row.get("updated_by"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+/// Analytics service for the dashboard's KPIs, read from materialized views
+/// a background job refreshes. Users see the departments they belong to;
+/// those who may audit see every department.
+pub struct AnalyticsService {
+    pool: PgPool,
+    role_service: RoleService,
+}
+
+impl AnalyticsService {
+    /// Create new analytics service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    fn require(permissions: &HashSet<Action>, actor: Uuid, action: Action) -> Result<(), HimsError> {
+        if !permissions.contains(&action) {
+            tracing::warn!("User {} lacks {} permission", actor, action);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", action),
+            });
+        }
+        Ok(())
+    }
+
+    /// The departments an analytics request covers, and its period
+    async fn scope(
+        &self,
+        actor: Uuid,
+        query: &AnalyticsQuery,
+        also: Option<Action>,
+    ) -> Result<(NaiveDate, NaiveDate, Option<Vec<Uuid>>), HimsError> {
+        let (from, to) = query.range(Utc::now().date_naive()).map_err(validation_error)?;
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        Self::require(&permissions, actor, Action::ViewAnalytics)?;
+        if let Some(action) = also {
+            Self::require(&permissions, actor, action)?;
+        }
+
+        let scope = if permissions.contains(&Action::Audit) {
+            DepartmentScope::All
+        } else {
+            let departments = sqlx::query_scalar::<_, Uuid>(GET_USER_DEPARTMENTS)
+                .bind(actor)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(database_error)?;
+            DepartmentScope::Departments(departments)
+        };
+        let departments = scope.restrict(query.department_id).map_err(|message| {
+            tracing::warn!("User {} asked for analytics outside their departments", actor);
+            HimsError::SecurityError { message }
+        })?;
+        Ok((from, to, departments))
+    }
+
+    /// Inpatient admissions by day and department
+    pub async fn admissions(&self, actor: Uuid, query: &AnalyticsQuery) -> Result<Vec<DailyAdmissions>, HimsError> {
+        let (from, to, departments) = self.scope(actor, query, None).await?;
+        let rows = sqlx::query(DAILY_ADMISSIONS)
+            .bind(from)
+            .bind(to)
+            .bind(departments)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| DailyAdmissions {
+                day: row.get("day"),
+                department_id: row.get("department_id"),
+                department: row.get("department"),
+                admissions: row.get("admissions"),
+            })
+            .collect())
+    }
+
+    /// Beds occupied at midnight by day and department
+    pub async fn bed_occupancy(&self, actor: Uuid, query: &AnalyticsQuery) -> Result<Vec<DailyOccupancy>, HimsError> {
+        let (from, to, departments) = self.scope(actor, query, None).await?;
+        let rows = sqlx::query(DAILY_CENSUS)
+            .bind(from)
+            .bind(to)
+            .bind(departments)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let occupied_beds: i32 = row.get("occupied_beds");
+                let beds: Option<i32> = row.get("beds");
+                DailyOccupancy {
+                    day: row.get("day"),
+                    department_id: row.get("department_id"),
+                    department: row.get("department"),
+                    occupied_beds,
+                    beds,
+                    occupancy_rate: beds.and_then(|beds| rate(occupied_beds.into(), beds.into(), 4)),
+                }
+            })
+            .collect())
+    }
+
+    async fn fetch_occupancy(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        departments: &Option<Vec<Uuid>>,
+    ) -> Result<Vec<DepartmentOccupancy>, HimsError> {
+        let days = (to - from).num_days() + 1;
+        let rows = sqlx::query(OCCUPANCY_BY_DEPARTMENT)
+            .bind(from)
+            .bind(to)
+            .bind(departments.clone())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(|row| department_occupancy_from_row(row, days)).collect())
+    }
+
+    async fn fetch_by_department<T>(
+        &self,
+        sql: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+        departments: &Option<Vec<Uuid>>,
+        from_row: fn(&PgRow) -> T,
+    ) -> Result<Vec<T>, HimsError> {
+        let rows = sqlx::query(sql)
+            .bind(from)
+            .bind(to)
+            .bind(departments.clone())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(from_row).collect())
+    }
+
+    /// Average length of stay by department, of stays discharged in the
+    /// period
+    pub async fn length_of_stay(&self, actor: Uuid, query: &AnalyticsQuery) -> Result<Vec<LengthOfStay>, HimsError> {
+        let (from, to, departments) = self.scope(actor, query, None).await?;
+        self.fetch_by_department(LENGTH_OF_STAY_BY_DEPARTMENT, from, to, &departments, length_of_stay_from_row)
+            .await
+    }
+
+    /// No-show rate by department, of appointments in the period
+    pub async fn no_show_rate(&self, actor: Uuid, query: &AnalyticsQuery) -> Result<Vec<NoShowRate>, HimsError> {
+        let (from, to, departments) = self.scope(actor, query, None).await?;
+        self.fetch_by_department(APPOINTMENTS_BY_DEPARTMENT, from, to, &departments, no_show_rate_from_row)
+            .await
+    }
+
+    /// Revenue by department, of charges for services in the period
+    pub async fn revenue(&self, actor: Uuid, query: &AnalyticsQuery) -> Result<Vec<DepartmentRevenue>, HimsError> {
+        let (from, to, departments) = self.scope(actor, query, Some(Action::ViewBilling)).await?;
+        self.fetch_by_department(REVENUE_BY_DEPARTMENT, from, to, &departments, department_revenue_from_row)
+            .await
+    }
+
+    /// Headline KPIs for the dashboard
+    pub async fn summary(&self, actor: Uuid, query: &AnalyticsQuery) -> Result<DashboardSummary, HimsError> {
+        let (from, to, departments) = self.scope(actor, query, None).await?;
+        let admissions: i64 = self
+            .fetch_by_department(ADMISSIONS_BY_DEPARTMENT, from, to, &departments, |row| {
+                row.get::<i64, _>("admissions")
+            })
+            .await?
+            .iter()
+            .sum();
+        let occupancy = self.fetch_occupancy(from, to, &departments).await?;
+        let stays = self
+            .fetch_by_department(LENGTH_OF_STAY_BY_DEPARTMENT, from, to, &departments, length_of_stay_from_row)
+            .await?;
+        let appointments = self
+            .fetch_by_department(APPOINTMENTS_BY_DEPARTMENT, from, to, &departments, no_show_rate_from_row)
+            .await?;
+        let revenue = if self.role_service.get_user_permissions(actor).await?.contains(&Action::ViewBilling) {
+            Some(
+                self.fetch_by_department(REVENUE_BY_DEPARTMENT, from, to, &departments, department_revenue_from_row)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        let mut summary = summarize(
+            from,
+            to,
+            departments,
+            admissions,
+            &occupancy,
+            &stays,
+            &appointments,
+            revenue.as_deref(),
+        );
+        summary.refreshed_at = sqlx::query_scalar(GET_REFRESHED_AT)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(summary)
+    }
+
+    /// Beds of each department, for users who may see analytics
+    pub async fn list_beds(&self, actor: Uuid) -> Result<Vec<DepartmentBeds>, HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        Self::require(&permissions, actor, Action::ViewAnalytics)?;
+        let rows = sqlx::query(LIST_DEPARTMENT_BEDS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(department_beds_from_row).collect())
+    }
+
+    /// Set the beds of a department. Occupancy is of the beds a department
+    /// has now, across the whole period asked for.
+    pub async fn set_beds(&self, department_id: Uuid, beds: i32, actor: Uuid) -> Result<DepartmentBeds, HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        Self::require(&permissions, actor, Action::Configure)?;
+        if beds < 0 {
+            return Err(validation_error("A department's beds cannot be negative".to_string()));
+        }
+        let exists: bool = sqlx::query_scalar(ORGANIZATION_EXISTS)
+            .bind(department_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+        if !exists {
+            return Err(validation_error(format!("Department {} is not an organization", department_id)));
+        }
+
+        let row = sqlx::query(UPSERT_DEPARTMENT_BEDS)
+            .bind(department_id)
+            .bind(beds)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        tracing::info!("Department {} set to {} beds by {}", department_id, beds, actor);
+        Ok(department_beds_from_row(&row))
+    }
+
+    /// Refresh the materialized views now, for users who may configure the
+    /// system
+    pub async fn refresh_now(&self, actor: Uuid) -> Result<bool, HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        Self::require(&permissions, actor, Action::Configure)?;
+        tracing::info!("Analytics refresh requested by {}", actor);
+        // The views hold every tenant's rows, so they are always computed
+        // with the tenant policy bypassed, never as the requesting tenant
+        with_tenant(TenantContext::system(), self.refresh()).await
+    }
+
+    /// Refresh the materialized views, unless another server is. Returns
+    /// whether they were refreshed.
+    pub async fn refresh(&self) -> Result<bool, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let locked: bool = sqlx::query_scalar(TRY_LOCK_REFRESH)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        if !locked {
+            tracing::debug!("Analytics refresh already running elsewhere");
+            return Ok(false);
+        }
+
+        for view in ANALYTICS_VIEWS {
+            let started = Instant::now();
+            sqlx::query(&format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {}", view))
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+            sqlx::query(RECORD_REFRESH)
+                .bind(view)
+                .bind(started.elapsed().as_millis().min(i32::MAX as u128) as i32)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+
+        tracing::info!("Refreshed {} analytics views", ANALYTICS_VIEWS.len());
+        Ok(true)
+    }
+
+}
+
+#[async_trait]
+impl ScheduledJob for AnalyticsService {
+    /// Refresh the materialized views, unless another server is
+    async fn run(&self) -> Result<Value, HimsError> {
+        Ok(json!({ "refreshed": self.refresh().await? }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_range_and_scope() {
+        let today = date("2024-03-31");
+        let query = AnalyticsQuery::default();
+        assert_eq!(query.range(today), Ok((date("2024-03-02"), today)));
+        let query = AnalyticsQuery { from: Some(date("2024-04-01")), ..Default::default() };
+        assert!(query.range(today).is_err());
+        let query = AnalyticsQuery { from: Some(date("2023-01-01")), ..Default::default() };
+        assert!(query.range(today).is_err(), "more than a year");
+
+        let (cardiology, oncology) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(DepartmentScope::All.restrict(None), Ok(None));
+        assert_eq!(DepartmentScope::All.restrict(Some(oncology)), Ok(Some(vec![oncology])));
+        let mine = DepartmentScope::Departments(vec![cardiology]);
+        assert_eq!(mine.restrict(None), Ok(Some(vec![cardiology])));
+        assert_eq!(mine.restrict(Some(cardiology)), Ok(Some(vec![cardiology])));
+        assert!(mine.restrict(Some(oncology)).is_err());
+        assert_eq!(DepartmentScope::Departments(vec![]).restrict(None), Ok(Some(vec![])));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let (from, to) = (date("2024-03-01"), date("2024-03-10"));
+        let department = |beds: Option<i32>, bed_days: i64| DepartmentOccupancy {
+            department_id: Some(Uuid::new_v4()),
+            department: None,
+            bed_days,
+            beds,
+            occupancy_rate: None,
+        };
+        // 20 beds over 10 days, 150 of them occupied; the unbedded ward does
+        // not count towards occupancy
+        let occupancy = [department(Some(20), 150), department(None, 40), department(Some(0), 0)];
+        let stay = |discharges: i64, patient_days: i64| LengthOfStay {
+            department_id: None,
+            department: None,
+            discharges,
+            patient_days,
+            average_length_of_stay: None,
+        };
+        let stays = [stay(4, 18), stay(2, 2)];
+        let appointments = [NoShowRate {
+            department_id: None,
+            department: None,
+            attended: 45,
+            no_shows: 5,
+            cancelled: 7,
+            no_show_rate: None,
+        }];
+        let revenue = [DepartmentRevenue { department_id: None, department: None, charges: 3, amount: 125_000 }];
+
+        let summary = summarize(from, to, None, 12, &occupancy, &stays, &appointments, Some(&revenue));
+        assert_eq!(summary.admissions, 12);
+        assert_eq!(summary.occupancy_rate, Some(0.75));
+        assert_eq!(summary.discharges, 6);
+        assert_eq!(summary.average_length_of_stay, Some(3.33));
+        assert_eq!(summary.no_show_rate, Some(0.1));
+        assert_eq!(summary.revenue, Some(125_000));
+
+        let empty = summarize(from, to, Some(vec![]), 0, &[], &[], &[], None);
+        assert_eq!(empty.occupancy_rate, None);
+        assert_eq!(empty.average_length_of_stay, None);
+        assert_eq!(empty.no_show_rate, None);
+        assert_eq!(empty.revenue, None);
+    }
+}
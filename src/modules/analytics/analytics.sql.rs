@@ -0,0 +1,167 @@
+//! Analytics SQL Queries
+//!
+//! This file contains all SQL queries used by the analytics service. The
+//! materialized views have no row-level security, so each query of them
+//! filters on the current tenant. `$3` is the departments a query is
+//! limited to, or NULL for all of them.
+
+/// Materialized views the refresh job keeps current
+pub const ANALYTICS_VIEWS: &[&str] = &[
+    "analytics_daily_admissions",
+    "analytics_daily_census",
+    "analytics_daily_discharges",
+    "analytics_daily_appointments",
+    "analytics_daily_revenue",
+];
+
+/// Take the refresh lock for the rest of the transaction, if no other
+/// server holds it
+pub const TRY_LOCK_REFRESH: &str = "SELECT pg_try_advisory_xact_lock(hashtext('analytics_refresh'))";
+
+/// Record when a view was refreshed
+pub const RECORD_REFRESH: &str = r#"
+    INSERT INTO analytics_refreshes (view_name, refreshed_at, duration_ms)
+    VALUES ($1, NOW(), $2)
+    ON CONFLICT (view_name) DO UPDATE
+    SET refreshed_at = EXCLUDED.refreshed_at, duration_ms = EXCLUDED.duration_ms
+"#;
+
+/// When the views were last all refreshed
+pub const GET_REFRESHED_AT: &str = r#"
+    SELECT MIN(refreshed_at) AS refreshed_at FROM analytics_refreshes
+"#;
+
+/// Departments the user is a member or head of, and the organization the
+/// user belongs to
+pub const GET_USER_DEPARTMENTS: &str = r#"
+    SELECT resource_id AS department_id
+    FROM authorization_relations
+    WHERE subject_type = 'user'
+    AND subject_id = $1
+    AND relation IN ('department_member', 'department_head')
+    AND resource_type IN ('department', 'organization')
+    AND is_active = true
+    AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+    UNION
+    SELECT organization_id FROM users WHERE id = $1 AND organization_id IS NOT NULL
+"#;
+
+/// Inpatient admissions by day and department
+pub const DAILY_ADMISSIONS: &str = r#"
+    SELECT a.day, a.department_id, o.name AS department, a.admissions
+    FROM analytics_daily_admissions a
+    LEFT JOIN organizations o ON o.id = a.department_id
+    WHERE a.tenant_id = current_tenant_id()
+    AND a.day BETWEEN $1 AND $2
+    AND ($3::uuid[] IS NULL OR a.department_id = ANY($3))
+    ORDER BY a.day, department
+"#;
+
+/// Beds occupied at midnight by day and department, with the department's
+/// beds
+pub const DAILY_CENSUS: &str = r#"
+    SELECT c.day, c.department_id, o.name AS department, c.occupied_beds, b.beds
+    FROM analytics_daily_census c
+    LEFT JOIN organizations o ON o.id = c.department_id
+    LEFT JOIN department_beds b ON b.department_id = c.department_id
+    WHERE c.tenant_id = current_tenant_id()
+    AND c.day BETWEEN $1 AND $2
+    AND ($3::uuid[] IS NULL OR c.department_id = ANY($3))
+    ORDER BY c.day, department
+"#;
+
+/// Occupied bed-days and beds by department, including departments with
+/// beds and none occupied
+pub const OCCUPANCY_BY_DEPARTMENT: &str = r#"
+    SELECT COALESCE(c.department_id, b.department_id) AS department_id, o.name AS department,
+           COALESCE(c.bed_days, 0)::bigint AS bed_days, b.beds
+    FROM (
+        SELECT department_id, sum(occupied_beds) AS bed_days
+        FROM analytics_daily_census
+        WHERE tenant_id = current_tenant_id()
+        AND day BETWEEN $1 AND $2
+        AND ($3::uuid[] IS NULL OR department_id = ANY($3))
+        GROUP BY department_id
+    ) c
+    FULL JOIN (
+        SELECT department_id, beds
+        FROM department_beds
+        WHERE ($3::uuid[] IS NULL OR department_id = ANY($3))
+    ) b ON b.department_id = c.department_id
+    LEFT JOIN organizations o ON o.id = COALESCE(c.department_id, b.department_id)
+    ORDER BY department
+"#;
+
+/// Admissions by department
+pub const ADMISSIONS_BY_DEPARTMENT: &str = r#"
+    SELECT a.department_id, o.name AS department, sum(a.admissions)::bigint AS admissions
+    FROM analytics_daily_admissions a
+    LEFT JOIN organizations o ON o.id = a.department_id
+    WHERE a.tenant_id = current_tenant_id()
+    AND a.day BETWEEN $1 AND $2
+    AND ($3::uuid[] IS NULL OR a.department_id = ANY($3))
+    GROUP BY a.department_id, o.name
+    ORDER BY department
+"#;
+
+/// Discharges and the days their stays lasted by department
+pub const LENGTH_OF_STAY_BY_DEPARTMENT: &str = r#"
+    SELECT d.department_id, o.name AS department, sum(d.discharges)::bigint AS discharges,
+           sum(d.patient_days)::bigint AS patient_days
+    FROM analytics_daily_discharges d
+    LEFT JOIN organizations o ON o.id = d.department_id
+    WHERE d.tenant_id = current_tenant_id()
+    AND d.day BETWEEN $1 AND $2
+    AND ($3::uuid[] IS NULL OR d.department_id = ANY($3))
+    GROUP BY d.department_id, o.name
+    ORDER BY department
+"#;
+
+/// Appointment outcomes by department
+pub const APPOINTMENTS_BY_DEPARTMENT: &str = r#"
+    SELECT a.department_id, o.name AS department, sum(a.attended)::bigint AS attended,
+           sum(a.no_shows)::bigint AS no_shows, sum(a.cancelled)::bigint AS cancelled
+    FROM analytics_daily_appointments a
+    LEFT JOIN organizations o ON o.id = a.department_id
+    WHERE a.tenant_id = current_tenant_id()
+    AND a.day BETWEEN $1 AND $2
+    AND ($3::uuid[] IS NULL OR a.department_id = ANY($3))
+    GROUP BY a.department_id, o.name
+    ORDER BY department
+"#;
+
+/// Charges captured by department
+pub const REVENUE_BY_DEPARTMENT: &str = r#"
+    SELECT r.department_id, o.name AS department, sum(r.charges)::bigint AS charges,
+           sum(r.amount)::bigint AS amount
+    FROM analytics_daily_revenue r
+    LEFT JOIN organizations o ON o.id = r.department_id
+    WHERE r.tenant_id = current_tenant_id()
+    AND r.day BETWEEN $1 AND $2
+    AND ($3::uuid[] IS NULL OR r.department_id = ANY($3))
+    GROUP BY r.department_id, o.name
+    ORDER BY department
+"#;
+
+/// Beds of each department
+pub const LIST_DEPARTMENT_BEDS: &str = r#"
+    SELECT b.department_id, o.name AS department, b.beds, b.updated_by, b.updated_at
+    FROM department_beds b
+    LEFT JOIN organizations o ON o.id = b.department_id
+    ORDER BY department
+"#;
+
+/// Set the beds of a department
+pub const UPSERT_DEPARTMENT_BEDS: &str = r#"
+    INSERT INTO department_beds (department_id, beds, updated_by)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (tenant_id, department_id) DO UPDATE
+    SET beds = EXCLUDED.beds, updated_by = EXCLUDED.updated_by
+    RETURNING department_id, (SELECT name FROM organizations WHERE id = $1) AS department, beds, updated_by,
+              updated_at
+"#;
+
+/// Check an organization exists
+pub const ORGANIZATION_EXISTS: &str = r#"
+    SELECT EXISTS (SELECT 1 FROM organizations WHERE id = $1)
+"#;
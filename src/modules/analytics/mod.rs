@@ -0,0 +1,51 @@
+//! Analytics Module
+//!
+//! This module serves the dashboard's KPIs including:
+//! - Daily admissions and midnight bed occupancy against department beds
+//! - Average length of stay, appointment no-show rate and revenue by department
+//! - Materialized views refreshed on a schedule by a background job
+//! - Scoping to the departments a user belongs to
+
+#[path = "analytics.controller.rs"]
+pub mod analytics_controller;
+#[path = "analytics.service.rs"]
+pub mod analytics_service;
+#[path = "analytics.sql.rs"]
+pub mod analytics_sql;
+
+pub use analytics_controller::AnalyticsController;
+pub use analytics_service::{AnalyticsQuery, AnalyticsService, DashboardSummary, DepartmentScope};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Analytics Module Configuration
+pub struct AnalyticsModule {
+    pub service: Arc<AnalyticsService>,
+    pub controller: Arc<AnalyticsController>,
+}
+
+impl AnalyticsModule {
+    /// Create a new Analytics Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(AnalyticsService::new(db_pool));
+        let controller = Arc::new(AnalyticsController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<AnalyticsService> {
+        self.service.clone()
+    }
+}
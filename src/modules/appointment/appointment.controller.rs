@@ -1,6 +1,6 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::Json,
     routing::{delete, get, post, put},
     Router,
@@ -8,26 +8,30 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 
 use crate::models::{Appointment, AppointmentStatus, ResourceMeta, CodeableConcept, 
                    AppointmentParticipant};
+use crate::modules::appointment::appointment_service::{AppointmentSearch, AppointmentSearchResult};
 use crate::modules::appointment::AppointmentService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::get_user_session_context;
+use crate::utils::etag::{if_match_version, precondition_status, versioned, Versioned};
+use crate::utils::fhir_search::{EntrySearch, SearchEntryMode};
+use crate::utils::pagination::{page_links, BundleLink};
 use std::sync::Arc;
 
+/// State of the appointment routes: the appointment service and the engine
+/// deciding who may see appointments
+type AppointmentState = (Arc<AppointmentService>, Arc<HimsAuthorizationEngine>);
+
 /// Appointment controller for FHIR R4 compliant appointment management
 pub struct AppointmentController {
     appointment_service: Arc<AppointmentService>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct AppointmentQuery {
-    pub status: Option<AppointmentStatus>,
-    pub date: Option<String>,
-    pub patient: Option<Uuid>,
-    pub practitioner: Option<Uuid>,
-    pub service_type: Option<String>,
-    pub _count: Option<u32>,
-    pub _offset: Option<u32>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,63 +51,83 @@ pub struct AppointmentCreateRequest {
 
 #[derive(Debug, Serialize)]
 pub struct AppointmentResponse {
-    pub resourceType: String,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
     pub id: Uuid,
     pub meta: ResourceMeta,
     pub status: AppointmentStatus,
-    pub serviceCategory: Vec<CodeableConcept>,
-    pub serviceType: Vec<CodeableConcept>,
+    #[serde(rename = "serviceCategory")]
+    pub service_category: Vec<CodeableConcept>,
+    #[serde(rename = "serviceType")]
+    pub service_type: Vec<CodeableConcept>,
     pub specialty: Vec<CodeableConcept>,
-    pub appointmentType: Option<CodeableConcept>,
-    pub reasonCode: Vec<CodeableConcept>,
+    #[serde(rename = "appointmentType")]
+    pub appointment_type: Option<CodeableConcept>,
+    #[serde(rename = "reasonCode")]
+    pub reason_code: Vec<CodeableConcept>,
     pub priority: Option<u32>,
     pub description: Option<String>,
     pub start: Option<DateTime<Utc>>,
     pub end: Option<DateTime<Utc>>,
-    pub minutesDuration: Option<u32>,
+    #[serde(rename = "minutesDuration")]
+    pub minutes_duration: Option<u32>,
     pub participant: Vec<AppointmentParticipant>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct AppointmentBundle {
-    pub resourceType: String,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
     pub id: Uuid,
     pub meta: ResourceMeta,
-    pub total: u32,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    pub link: Vec<BundleLink>,
     pub entry: Vec<AppointmentBundleEntry>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct AppointmentBundleEntry {
-    pub fullUrl: String,
-    pub resource: AppointmentResponse,
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    /// An appointment, or a patient or practitioner added by `_include`
+    pub resource: serde_json::Value,
+    pub search: EntrySearch,
 }
 
 impl AppointmentController {
-    /// Create new controller with injected service
-    pub fn new(appointment_service: Arc<AppointmentService>) -> Self {
-        Self { appointment_service }
+    /// Create new controller with injected service and authorization engine
+    pub fn new(appointment_service: Arc<AppointmentService>, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        Self {
+            appointment_service,
+            authorization_engine,
+        }
     }
 
     /// Create router with dependency injection
-    pub fn routes(&self) -> Router {
-        Router::new()
-            .route("/", post(Self::create_appointment))
-            .route("/", get(Self::search_appointments))
-            .route("/:id", get(Self::get_appointment))
-            .route("/:id", put(Self::update_appointment))
-            .route("/:id", delete(Self::delete_appointment))
-            .with_state(self.appointment_service.clone())
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_appointment, "Create new appointment")
+            .get("/", Self::search_appointments, "Search appointments with query parameters")
+            .get("/:id", Self::get_appointment, "Get appointment by ID")
+            .put("/:id", Self::update_appointment, "Update appointment")
+            .delete("/:id", Self::delete_appointment, "Delete appointment")
+            .with_state((self.appointment_service.clone(), self.authorization_engine.clone()))
     }
 
     /// Create new appointment
     pub async fn create_appointment(
-        State(appointment_service): State<Arc<AppointmentService>>,
+        State((appointment_service, _)): State<AppointmentState>,
+        auth: AuthContext,
         Json(payload): Json<AppointmentCreateRequest>,
     ) -> Result<(StatusCode, Json<AppointmentResponse>), (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Creating new appointment");
         
-        match appointment_service.create_appointment_from_request(payload, "system").await {
+        match appointment_service.create_appointment_from_request(payload, &auth).await {
             Ok(appointment) => {
                 tracing::info!("Appointment created successfully: {}", appointment.id);
                 Ok((StatusCode::CREATED, Json(Self::appointment_to_response(appointment))))
@@ -121,17 +145,31 @@ impl AppointmentController {
         }
     }
 
-    /// Get appointment by ID
+    /// Get appointment by ID; reading it needs the same access as reading
+    /// its patient
     pub async fn get_appointment(
-        State(appointment_service): State<Arc<AppointmentService>>,
+        State((appointment_service, authorization_engine)): State<AppointmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Path(id): Path<Uuid>,
-    ) -> Result<Json<AppointmentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<Versioned<AppointmentResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Retrieving appointment: {}", id);
         
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        let patients = Self::patients(&appointment_service, &[id]).await?;
+        if Self::readable(&authorizer, &auth, &patients, vec![id], |id| *id).await?.is_empty() {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "Access denied".to_string(),
+                    message: format!("Appointment {} is not available to you", id),
+                }),
+            ));
+        }
         match appointment_service.get_appointment_by_uuid(id).await {
             Ok(Some(appointment)) => {
                 tracing::info!("Appointment retrieved successfully: {}", id);
-                Ok(Json(Self::appointment_to_response(appointment)))
+                Ok(versioned(&appointment.meta.clone(), Self::appointment_to_response(appointment)))
             }
             Ok(None) => {
                 tracing::warn!("Appointment not found: {}", id);
@@ -156,17 +194,46 @@ impl AppointmentController {
         }
     }
 
-    /// Search appointments with query parameters
+    /// Search appointments with query parameters. Appointments the user may
+    /// not read are left out, with their included patients, so a page can
+    /// be short; follow `next` until there is none.
     pub async fn search_appointments(
-        State(appointment_service): State<Arc<AppointmentService>>,
-        Query(params): Query<AppointmentQuery>,
+        State((appointment_service, authorization_engine)): State<AppointmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
     ) -> Result<Json<AppointmentBundle>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Searching appointments with params: {:?}", params);
-        
-        match appointment_service.search_appointments_from_query(params).await {
-            Ok(appointments) => {
-                tracing::info!("Found {} appointments", appointments.len());
-                Ok(Json(Self::appointments_to_bundle(appointments)))
+
+        let search = AppointmentSearch::from_params(&params).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid search parameters".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+        })?;
+
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        match appointment_service.search(&search).await {
+            Ok(mut result) => {
+                let ids: Vec<Uuid> = result.appointments.iter().map(|appointment| appointment.id).collect();
+                let patients = Self::patients(&appointment_service, &ids).await?;
+                result.appointments =
+                    Self::readable(&authorizer, &auth, &patients, result.appointments, |appointment| appointment.id).await?;
+                let shown: HashSet<Uuid> = result
+                    .appointments
+                    .iter()
+                    .filter_map(|appointment| patients.get(&appointment.id).copied())
+                    .collect();
+                result
+                    .included
+                    .retain(|included| included.resource_type != "Patient" || shown.contains(&included.id));
+                tracing::info!("Found {} appointments", result.appointments.len());
+                let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+                Ok(Json(Self::appointments_to_bundle(result, link)))
             }
             Err(e) => {
                 tracing::error!("Failed to search appointments: {}", e);
@@ -181,23 +248,35 @@ impl AppointmentController {
         }
     }
 
-    /// Update appointment
+    /// Update appointment; `If-Match` must name the version being replaced
     pub async fn update_appointment(
-        State(appointment_service): State<Arc<AppointmentService>>,
+        State((appointment_service, _)): State<AppointmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Path(id): Path<Uuid>,
         Json(payload): Json<AppointmentCreateRequest>,
-    ) -> Result<Json<AppointmentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<Versioned<AppointmentResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Updating appointment: {}", id);
         
-        match appointment_service.update_appointment_from_request(id, payload).await {
+        let expected_version = if_match_version(&headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the appointment's ETag in If-Match to update it".to_string(),
+                }),
+            )
+        })?;
+        
+        match appointment_service.update_appointment_from_request(id, payload, &expected_version, &auth).await {
             Ok(appointment) => {
                 tracing::info!("Appointment updated successfully: {}", id);
-                Ok(Json(Self::appointment_to_response(appointment)))
+                Ok(versioned(&appointment.meta.clone(), Self::appointment_to_response(appointment)))
             }
             Err(e) => {
                 tracing::error!("Failed to update appointment {}: {}", id, e);
                 Err((
-                    StatusCode::BAD_REQUEST,
+                    precondition_status(&e).unwrap_or(StatusCode::BAD_REQUEST),
                     Json(ErrorResponse {
                         error: "Failed to update appointment".to_string(),
                         message: e.to_string(),
@@ -209,12 +288,13 @@ impl AppointmentController {
 
     /// Cancel appointment
     pub async fn cancel_appointment(
-        State(appointment_service): State<Arc<AppointmentService>>,
+        State((appointment_service, _)): State<AppointmentState>,
+        auth: AuthContext,
         Path(id): Path<Uuid>,
     ) -> Result<Json<AppointmentResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Cancelling appointment: {}", id);
         
-        match appointment_service.cancel_appointment_by_uuid(id).await {
+        match appointment_service.cancel_appointment_by_uuid(id, &auth).await {
             Ok(appointment) => {
                 tracing::info!("Appointment cancelled successfully: {}", id);
                 Ok(Json(Self::appointment_to_response(appointment)))
@@ -234,12 +314,13 @@ impl AppointmentController {
 
     /// Delete appointment
     pub async fn delete_appointment(
-        State(appointment_service): State<Arc<AppointmentService>>,
+        State((appointment_service, _)): State<AppointmentState>,
+        auth: AuthContext,
         Path(id): Path<Uuid>,
     ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Deleting appointment: {}", id);
         
-        match appointment_service.delete_appointment(&id.to_string(), "system").await {
+        match appointment_service.delete_appointment(&id.to_string(), &auth).await {
             Ok(true) => {
                 tracing::info!("Appointment deleted successfully: {}", id);
                 Ok(StatusCode::NO_CONTENT)
@@ -267,40 +348,118 @@ impl AppointmentController {
         }
     }
 
+    /// Authorizer for the signed-in user and the purpose of use the request
+    /// declares
+    async fn authorizer(
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+    ) -> Result<FieldAuthorizer, (StatusCode, Json<ErrorResponse>)> {
+        let context = get_user_session_context(auth, headers).await.map_err(|e| {
+            tracing::error!("Failed to get user session context: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Internal server error".to_string(),
+                    message: "Failed to establish session context".to_string(),
+                }),
+            )
+        })?;
+        Ok(FieldAuthorizer::for_request(authorization_engine, auth, context))
+    }
+
+    /// Patients of the appointments, by appointment
+    async fn patients(
+        appointment_service: &AppointmentService,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Uuid>, (StatusCode, Json<ErrorResponse>)> {
+        appointment_service.appointment_patients(ids).await.map_err(|e| {
+            tracing::error!("Failed to look up patients of appointments: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Internal server error".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+        })
+    }
+
+    /// The appointments among `items` the user may read, by the ID
+    /// `appointment_id` gives each; the others are withheld. An appointment
+    /// is checked against its patient, or on its own when it has none.
+    async fn readable<T>(
+        authorizer: &FieldAuthorizer,
+        auth: &AuthContext,
+        patients: &HashMap<Uuid, Uuid>,
+        items: Vec<T>,
+        appointment_id: impl Fn(&T) -> Uuid,
+    ) -> Result<Vec<T>, (StatusCode, Json<ErrorResponse>)> {
+        let found = items.len();
+        let readable = authorizer
+            .retain(Action::Read, items, |item| {
+                let id = appointment_id(item);
+                patients.get(&id).map_or(Resource::Appointment(id), |patient_id| Resource::Patient(*patient_id))
+            })
+            .await
+            .map_err(|e| {
+                tracing::error!("Authorization check failed: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Authorization error".to_string(),
+                        message: "Failed to check authorization".to_string(),
+                    }),
+                )
+            })?;
+        if readable.len() < found {
+            let withheld = found - readable.len();
+            tracing::info!("Withheld {} of {} appointments from user {}", withheld, found, auth.user_id);
+        }
+        Ok(readable)
+    }
+
     /// Convert Appointment model to FHIR response format
     fn appointment_to_response(appointment: Appointment) -> AppointmentResponse {
         AppointmentResponse {
-            resourceType: "Appointment".to_string(),
+            resource_type: "Appointment".to_string(),
             id: appointment.id,
             meta: appointment.meta,
             status: appointment.status,
-            serviceCategory: appointment.service_category,
-            serviceType: appointment.service_type,
+            service_category: appointment.service_category,
+            service_type: appointment.service_type,
             specialty: appointment.specialty,
-            appointmentType: appointment.appointment_type,
-            reasonCode: appointment.reason_code,
+            appointment_type: appointment.appointment_type,
+            reason_code: appointment.reason_code,
             priority: appointment.priority,
             description: appointment.description,
             start: appointment.start,
             end: appointment.end,
-            minutesDuration: appointment.minutes_duration,
+            minutes_duration: appointment.minutes_duration,
             participant: appointment.participant,
         }
     }
 
-    /// Convert multiple appointments to FHIR Bundle format
-    fn appointments_to_bundle(appointments: Vec<Appointment>) -> AppointmentBundle {
-        let total = appointments.len() as u32;
-        let entries: Vec<AppointmentBundleEntry> = appointments
-            .into_iter()
-            .map(|appointment| AppointmentBundleEntry {
-                fullUrl: format!("Appointment/{}", appointment.id),
-                resource: Self::appointment_to_response(appointment),
-            })
-            .collect();
+    /// Convert a page of search results to a FHIR searchset Bundle
+    fn appointments_to_bundle(result: AppointmentSearchResult, link: Vec<BundleLink>) -> AppointmentBundle {
+        let matches = result.appointments.into_iter().map(|appointment| AppointmentBundleEntry {
+            full_url: format!("Appointment/{}", appointment.id),
+            resource: serde_json::to_value(Self::appointment_to_response(appointment)).unwrap_or_default(),
+            search: EntrySearch {
+                mode: SearchEntryMode::Match,
+            },
+        });
+        let included = result.included.into_iter().map(|included| AppointmentBundleEntry {
+            full_url: format!("{}/{}", included.resource_type, included.id),
+            resource: included.body(),
+            search: EntrySearch {
+                mode: SearchEntryMode::Include,
+            },
+        });
+        let entries: Vec<AppointmentBundleEntry> = matches.chain(included).collect();
 
         AppointmentBundle {
-            resourceType: "Bundle".to_string(),
+            resource_type: "Bundle".to_string(),
             id: Uuid::new_v4(),
             meta: ResourceMeta {
                 version_id: Some("1".to_string()),
@@ -309,13 +468,15 @@ impl AppointmentController {
                 security: vec![],
                 tag: vec![],
             },
-            total,
+            bundle_type: "searchset".to_string(),
+            total: result.total,
+            link,
             entry: entries,
         }
     }
 
     /// Create router for appointment endpoints
-    pub fn router() -> Router<Arc<AppointmentService>> {
+    pub fn router() -> Router<AppointmentState> {
         Router::new()
             .route("/", post(Self::create_appointment))
             .route("/", get(Self::search_appointments))
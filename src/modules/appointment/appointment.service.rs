@@ -1,13 +1,22 @@
 use anyhow::Result;
-use chrono::Utc;
-use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
 use serde_json;
 use uuid::Uuid;
 
-use crate::models::{Appointment, AuditLog, AuditEventType, AuditResourceType};
+use crate::models::{Appointment, AuditAction, AuditLog, AuditEventType};
 use crate::models::types::enums::AppointmentStatus;
 use crate::models::types::fhir::ResourceMeta;
 use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::modules::patient::PatientSearch;
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{
+    reference_id, split_chain, split_modifier, DateParam, IncludeParam, IncludedResource, SearchParamDefinition,
+    TotalMode,
+};
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
 
 // Import SQL queries from separate file
 use crate::modules::appointment::appointment_sql::*;
@@ -38,16 +47,221 @@ impl Default for AppointmentSearchFilters {
     }
 }
 
-impl From<crate::modules::appointment::appointment_controller::AppointmentQuery> for AppointmentSearchFilters {
-    fn from(query: crate::modules::appointment::appointment_controller::AppointmentQuery) -> Self {
+/// Page size of an appointment search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 50;
+
+/// Largest page an appointment search returns
+pub const MAX_SEARCH_COUNT: i64 = 100;
+
+/// Paging of appointment searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Order of appointment searches, latest first; SEARCH_APPOINTMENT_MATCHES
+/// numbers and keys rows the same way
+const SEARCH_KEYS: [SortKey; 1] = [SortKey::new("start_time", "timestamptz", true)];
+
+/// Referenced resources `_include` can add to an appointment search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppointmentInclude {
+    Patient,
+    Practitioner,
+}
+
+impl AppointmentInclude {
+    /// Includes named by `Appointment:patient`, `Appointment:practitioner`
+    /// or `Appointment:actor[:Type]`
+    pub fn from_param(include: &IncludeParam) -> Option<Vec<Self>> {
+        if include.source != "Appointment" {
+            return None;
+        }
+        let target = include.target.as_deref();
+        match (include.param.as_str(), target) {
+            ("patient", None | Some("Patient")) => Some(vec![AppointmentInclude::Patient]),
+            ("practitioner", None | Some("Practitioner")) => Some(vec![AppointmentInclude::Practitioner]),
+            ("actor", None) => Some(vec![AppointmentInclude::Patient, AppointmentInclude::Practitioner]),
+            ("actor", Some("Patient")) => Some(vec![AppointmentInclude::Patient]),
+            ("actor", Some("Practitioner")) => Some(vec![AppointmentInclude::Practitioner]),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed FHIR Appointment search
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppointmentSearch {
+    /// Any of these statuses
+    pub status: Vec<String>,
+    /// Ranges the start must fall in
+    pub date: Vec<DateParam>,
+    /// Any of these patients
+    pub patient: Vec<Uuid>,
+    /// Any of these practitioners
+    pub practitioner: Vec<Uuid>,
+    /// Criteria on the appointment's patient, from `patient.<param>`
+    pub patient_chain: Option<PatientSearch>,
+    pub include: Vec<AppointmentInclude>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for AppointmentSearch {
+    fn default() -> Self {
         Self {
-            status: query.status.map(|s| s.to_string()),
-            start_date: None, // Would need date parsing
-            end_date: None,
-            patient_id: query.patient.map(|p| p.to_string()),
-            practitioner_id: query.practitioner.map(|p| p.to_string()),
-            limit: query._count,
-            offset: query._offset,
+            status: Vec::new(),
+            date: Vec::new(),
+            patient: Vec::new(),
+            practitioner: Vec::new(),
+            patient_chain: None,
+            include: Vec::new(),
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// A page of appointment search results
+#[derive(Debug)]
+pub struct AppointmentSearchResult {
+    pub appointments: Vec<Appointment>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    /// Patients and practitioners from `_include`
+    pub included: Vec<IncludedResource>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl AppointmentSearch {
+    /// Search parameters `from_params` supports
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "status",
+            param_type: "token",
+            documentation: "Appointment status; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "date",
+            param_type: "date",
+            documentation: "Start of the appointment; supports eq, ne, gt, lt, ge and le prefixes",
+        },
+        SearchParamDefinition {
+            name: "patient",
+            param_type: "reference",
+            documentation: "The patient; chains to Patient search parameters (patient.name=smith)",
+        },
+        SearchParamDefinition {
+            name: "practitioner",
+            param_type: "reference",
+            documentation: "The practitioner",
+        },
+        SearchParamDefinition {
+            name: "actor",
+            param_type: "reference",
+            documentation: "A participant, typed as actor:Patient or actor:Practitioner",
+        },
+    ];
+
+    /// `_include` values `from_params` supports
+    pub const INCLUDES: &'static [&'static str] = &[
+        "Appointment:patient",
+        "Appointment:practitioner",
+        "Appointment:actor",
+        "Appointment:actor:Patient",
+        "Appointment:actor:Practitioner",
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored; invalid values
+    /// of supported ones, and unsupported chains or includes, are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let mut patient_chain = Vec::new();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+
+        for (param, value) in params {
+            if let Some(chain) = split_chain(param) {
+                match (chain.reference, chain.target) {
+                    ("patient", None | Some("Patient")) | ("actor", Some("Patient")) => {}
+                    _ => {
+                        return Err(HimsError::ValidationError {
+                            message: format!("Unsupported chained parameter {}", param),
+                        })
+                    }
+                }
+                if chain.param.starts_with('_') && chain.param != "_id" {
+                    return Err(invalid(param, value));
+                }
+                patient_chain.push((chain.param.to_string(), value.clone()));
+                continue;
+            }
+
+            let (name, modifier) = split_modifier(param);
+            match (name, modifier) {
+                ("status", None) => {
+                    for status in value.split(',') {
+                        status.parse::<AppointmentStatus>().map_err(|_| invalid(name, status))?;
+                        search.status.push(status.to_string());
+                    }
+                }
+                ("date", None) => search.date.push(DateParam::parse(value)?),
+                ("patient", None | Some("Patient")) | ("actor", Some("Patient")) => {
+                    for reference in value.split(',') {
+                        search.patient.push(reference_id(reference, "Patient")?);
+                    }
+                }
+                ("practitioner", None | Some("Practitioner")) | ("actor", Some("Practitioner")) => {
+                    for reference in value.split(',') {
+                        search.practitioner.push(reference_id(reference, "Practitioner")?);
+                    }
+                }
+                ("actor", None) => {
+                    return Err(HimsError::ValidationError {
+                        message: "actor needs a type, as actor:Patient or actor:Practitioner".to_string(),
+                    })
+                }
+                ("_include", _) => {
+                    let include = IncludeParam::parse(value)?;
+                    for include in AppointmentInclude::from_param(&include).ok_or_else(|| invalid(name, value))? {
+                        if !search.include.contains(&include) {
+                            search.include.push(include);
+                        }
+                    }
+                }
+                ("_revinclude", _) => {
+                    return Err(HimsError::ValidationError {
+                        message: format!("No supported resource refers to Appointment: {}", value),
+                    })
+                }
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported appointment search parameter {}", param),
+            }
+        }
+
+        if !patient_chain.is_empty() {
+            search.patient_chain = Some(PatientSearch::from_params(&patient_chain)?);
+        }
+        search.page.check_cursor(&SEARCH_KEYS)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on appointments for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if !self.status.is_empty() {
+            query.push(" AND status = ANY(").push_bind(self.status.clone()).push(")");
+        }
+        for date in &self.date {
+            date.push_condition(query, "start_time");
+        }
+        if !self.patient.is_empty() {
+            query.push(" AND patient_id = ANY(").push_bind(self.patient.clone()).push(")");
+        }
+        if !self.practitioner.is_empty() {
+            query.push(" AND practitioner_id = ANY(").push_bind(self.practitioner.clone()).push(")");
+        }
+        if let Some(chain) = &self.patient_chain {
+            query.push(" AND patient_id IN (SELECT id FROM patients");
+            chain.push_filters(query);
+            query.push(")");
         }
     }
 }
@@ -76,18 +290,24 @@ impl From<crate::modules::appointment::appointment_controller::AppointmentCreate
 /// Service for managing appointments with FHIR compliance and audit logging
 pub struct AppointmentService {
     pool: PgPool,
+    events: EventBus,
 }
 
 impl AppointmentService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self::with_events(pool, EventBus::default())
+    }
+
+    /// Create the service publishing to a shared event bus
+    pub fn with_events(pool: PgPool, events: EventBus) -> Self {
+        Self { pool, events }
     }
 
     /// Create a new appointment with audit logging
     pub async fn create_appointment(
         &self,
         appointment: &Appointment,
-        user_id: &str,
+        auth: &AuthContext,
     ) -> Result<String, HimsError> {
         let mut tx = self.pool.begin().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
@@ -99,13 +319,14 @@ impl AppointmentService {
             .bind(&appointment_id)
             .bind("") // patient_id placeholder
             .bind("") // practitioner_id placeholder
-            .bind(&appointment.start)
-            .bind(&appointment.end)
-            .bind(&appointment.status.to_string())
+            .bind(appointment.start)
+            .bind(appointment.end)
+            .bind(appointment.status.to_string())
             .bind("") // service_type placeholder
-            .bind(&appointment.description.as_deref().unwrap_or(""))
+            .bind(appointment.description.as_deref().unwrap_or(""))
             .bind(chrono::Utc::now())
             .bind(chrono::Utc::now())
+            .bind(serde_json::to_value(&appointment.meta).map_err(|e| HimsError::InternalError { message: e.to_string() })?)
             .execute(&mut *tx)
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
@@ -114,13 +335,14 @@ impl AppointmentService {
         self.create_audit_log(
             &mut tx,
             &appointment_id,
-            user_id,
+            auth,
             AuditEventType::Create,
             Some("Appointment created".to_string()),
         ).await?;
 
         tx.commit().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.events.publish(DomainEvent::AppointmentBooked { appointment_id: appointment.id });
         
         Ok(appointment_id)
     }
@@ -129,23 +351,95 @@ impl AppointmentService {
     pub async fn create_appointment_from_request(
         &self,
         request: crate::modules::appointment::appointment_controller::AppointmentCreateRequest,
-        user_id: &str,
+        auth: &AuthContext,
     ) -> Result<Appointment, HimsError> {
         let appointment: Appointment = request.into();
-        let appointment_id = self.create_appointment(&appointment, user_id).await?;
+        let appointment_id = self.create_appointment(&appointment, auth).await?;
         
         // Return the created appointment
         self.get_appointment(&appointment_id).await?
             .ok_or(HimsError::DatabaseError("Created appointment not found".to_string()))
     }
 
-    /// Search appointments from controller query
-    pub async fn search_appointments_from_query(
-        &self,
-        query: crate::modules::appointment::appointment_controller::AppointmentQuery,
-    ) -> Result<Vec<Appointment>, HimsError> {
-        let filters: AppointmentSearchFilters = query.into();
-        self.search_appointments(filters).await
+    /// FHIR search, with any `_include`d resources read in the same query
+    pub async fn search(&self, search: &AppointmentSearch) -> Result<AppointmentSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_APPOINTMENTS_PAGE);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &SEARCH_KEYS);
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+        query.push("), page AS (SELECT * FROM fetched");
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.count).push(")");
+        query.push(SEARCH_APPOINTMENT_MATCHES);
+        for include in &search.include {
+            query.push(match include {
+                AppointmentInclude::Patient => INCLUDE_APPOINTMENT_PATIENTS,
+                AppointmentInclude::Practitioner => INCLUDE_APPOINTMENT_PRACTITIONERS,
+            });
+        }
+        query.push(" ORDER BY position NULLS LAST");
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut appointments = Vec::new();
+        let mut included = Vec::new();
+        let mut more = false;
+        for row in rows {
+            let resource_type: String = row.get("resource_type");
+            if row.get::<String, _>("search_mode") == "match" {
+                more = row.get::<i64, _>("fetched") > search.page.count;
+                let key = RowKey::from_column(row.get("page_key"))?;
+                let appointment = Appointment {
+                    id: row.get::<String, _>("id").parse().unwrap_or_else(|_| Uuid::new_v4()),
+                    start: row.get("start_time"),
+                    end: row.get("end_time"),
+                    status: row.get::<String, _>("status").parse().unwrap_or_default(),
+                    service_category: vec![],
+                    service_type: vec![],
+                    specialty: vec![],
+                    appointment_type: None,
+                    reason_code: vec![],
+                    priority: None,
+                    description: None,
+                    minutes_duration: None,
+                    participant: vec![],
+                    meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
+                };
+                appointments.push((appointment, key));
+            } else {
+                included.push(IncludedResource {
+                    resource_type,
+                    id: row.get::<String, _>("id").parse().map_err(|e: uuid::Error| HimsError::DatabaseError(e.to_string()))?,
+                    resource: row.get("resource"),
+                });
+            }
+        }
+
+        // Matches arrive in list order whichever way the page was fetched
+        let page = search.page.page_in_order(appointments, more);
+
+        let total = count_matches(
+            &self.pool,
+            search.page.total(),
+            COUNT_APPOINTMENTS,
+            ESTIMATE_APPOINTMENTS,
+            |query| search.push_filters(query),
+        )
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(AppointmentSearchResult {
+            appointments: page.items,
+            total,
+            included,
+            next: page.next,
+            previous: page.previous,
+        })
     }
 
     /// Get appointment by UUID (adapter for controller)
@@ -153,19 +447,31 @@ impl AppointmentService {
         self.get_appointment(&id.to_string()).await
     }
 
+    /// Patients of the appointments that have one, by appointment
+    pub async fn appointment_patients(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Uuid>, HimsError> {
+        let rows: Vec<(Uuid, Uuid)> = sqlx::query_as(GET_APPOINTMENT_PATIENTS)
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(rows.into_iter().collect())
+    }
+
     /// Update appointment from controller request
     pub async fn update_appointment_from_request(
         &self,
         id: Uuid,
         request: crate::modules::appointment::appointment_controller::AppointmentCreateRequest,
+        expected_version: &str,
+        auth: &AuthContext,
     ) -> Result<Appointment, HimsError> {
         let appointment: Appointment = request.into();
-        self.update_appointment(id, appointment).await
+        self.update_appointment(id, appointment, expected_version, auth).await
     }
 
     /// Cancel appointment by UUID (adapter for controller)
-    pub async fn cancel_appointment_by_uuid(&self, id: Uuid) -> Result<Appointment, HimsError> {
-        self.cancel_appointment(id).await
+    pub async fn cancel_appointment_by_uuid(&self, id: Uuid, auth: &AuthContext) -> Result<Appointment, HimsError> {
+        self.cancel_appointment(id, auth).await
     }
 
     /// Retrieve appointment by ID
@@ -191,7 +497,7 @@ impl AppointmentService {
                 description: None,
                 minutes_duration: None,
                 participant: vec![],
-                meta: Default::default(),
+                meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
             }))
         } else {
             Ok(None)
@@ -225,7 +531,7 @@ impl AppointmentService {
                 description: None,
                 minutes_duration: None,
                 participant: vec![],
-                meta: Default::default(),
+                meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
             }
         }).collect();
 
@@ -237,7 +543,7 @@ impl AppointmentService {
         &self,
         id: &str,
         status: &str,
-        user_id: &str,
+        auth: &AuthContext,
     ) -> Result<bool, HimsError> {
         let mut tx = self.pool.begin().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
@@ -254,13 +560,16 @@ impl AppointmentService {
             self.create_audit_log(
                 &mut tx,
                 id,
-                user_id,
+                auth,
                 AuditEventType::Update,
                 Some(format!("Appointment status updated to: {}", status)),
             ).await?;
 
             tx.commit().await
                 .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            if let Ok(appointment_id) = Uuid::parse_str(id) {
+                self.events.publish(DomainEvent::AppointmentStatusChanged { appointment_id, status: status.to_string() });
+            }
             Ok(true)
         } else {
             Ok(false)
@@ -268,7 +577,7 @@ impl AppointmentService {
     }
 
     /// Soft delete appointment with audit logging
-    pub async fn delete_appointment(&self, id: &str, user_id: &str) -> Result<bool, HimsError> {
+    pub async fn delete_appointment(&self, id: &str, auth: &AuthContext) -> Result<bool, HimsError> {
         let mut tx = self.pool.begin().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
@@ -283,111 +592,102 @@ impl AppointmentService {
             self.create_audit_log(
                 &mut tx,
                 id,
-                user_id,
+                auth,
                 AuditEventType::Delete,
                 Some("Appointment soft deleted".to_string()),
             ).await?;
 
             tx.commit().await
                 .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            if let Ok(appointment_id) = Uuid::parse_str(id) {
+                self.events.publish(DomainEvent::AppointmentDeleted { appointment_id });
+            }
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
-    /// Private helper for audit logging
-    async fn log_appointment_audit(
-        &self,
-        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-        appointment_id: &str,
-        user_id: &str,
-        event_type: AuditEventType,
-        details: Option<String>,
-    ) -> Result<(), HimsError> {
-        let audit_log = AuditLog {
-            id: Uuid::new_v4().to_string(),
-            event_type,
-            user_id: user_id.to_string(),
-            patient_id: None,
-            appointment_id: Some(appointment_id.to_string()),
-            resource_type: AuditResourceType::Appointment,
-            resource_id: appointment_id.to_string(),
-            action: "CRUD".to_string(),
-            outcome: "Success".to_string(),
-            timestamp: Utc::now(),
-            source_ip: None,
-            user_agent: None,
-            details,
-        };
-
-        sqlx::query(CREATE_AUDIT_LOG)
-            .bind(&audit_log.id)
-            .bind(audit_log.event_type.to_string())
-            .bind(&audit_log.user_id)
-            .bind(audit_log.patient_id.as_ref())
-            .bind(audit_log.appointment_id.as_ref())
-            .bind(audit_log.resource_type.to_string())
-            .bind(&audit_log.resource_id)
-            .bind(&audit_log.action)
-            .bind(&audit_log.outcome)
-            .bind(audit_log.timestamp)
-            .bind(audit_log.source_ip.as_ref())
-            .bind(audit_log.user_agent.as_ref())
-            .bind(audit_log.details.as_ref())
-            .execute(&mut **transaction)
-            .await
-            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
-
-        Ok(())
-    }
-
-    /// Update appointment
+    /// Update appointment if it is still at `expected_version`, bumping its
+    /// version
     pub async fn update_appointment(
         &self,
         id: Uuid,
         updated_appointment: Appointment,
+        expected_version: &str,
+        auth: &AuthContext,
     ) -> Result<Appointment, HimsError> {
         let mut tx = self.pool.begin().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
+        let current_version: String = sqlx::query_scalar(GET_APPOINTMENT_VERSION)
+            .bind(id.to_string())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or(HimsError::DatabaseError(format!("Appointment not found: {}", id)))?;
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Appointment {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+        let previous_status: String = sqlx::query_scalar(GET_APPOINTMENT_STATUS)
+            .bind(id.to_string())
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
         // Update appointment
-        sqlx::query(UPDATE_APPOINTMENT)
-            .bind(&updated_appointment.start)
-            .bind(&updated_appointment.end)
-            .bind(&updated_appointment.status.to_string())
+        let rows_affected = sqlx::query(UPDATE_APPOINTMENT)
+            .bind(updated_appointment.start)
+            .bind(updated_appointment.end)
+            .bind(updated_appointment.status.to_string())
             .bind("") // patient_id placeholder
             .bind("") // practitioner_id placeholder  
             .bind("") // service_type placeholder
-            .bind(&updated_appointment.description.as_deref().unwrap_or(""))
-            .bind(&id.to_string())
+            .bind(updated_appointment.description.as_deref().unwrap_or(""))
+            .bind(id.to_string())
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
             .execute(&mut *tx)
             .await
-            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Appointment {} was modified concurrently", id),
+            });
+        }
 
         // Create audit log
         self.create_audit_log(
             &mut tx,
             &id.to_string(),
-            "system",
+            auth,
             AuditEventType::Update,
             Some("Appointment updated".to_string()),
         ).await?;
 
         tx.commit().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.events.publish(DomainEvent::AppointmentUpdated { appointment_id: id });
+        let status = updated_appointment.status.to_string();
+        if status != previous_status {
+            self.events.publish(DomainEvent::AppointmentStatusChanged { appointment_id: id, status });
+        }
 
-        Ok(updated_appointment)
+        self.get_appointment(&id.to_string()).await?
+            .ok_or(HimsError::DatabaseError("Appointment not found after update".to_string()))
     }
 
     /// Cancel appointment
-    pub async fn cancel_appointment(&self, id: Uuid) -> Result<Appointment, HimsError> {
+    pub async fn cancel_appointment(&self, id: Uuid, auth: &AuthContext) -> Result<Appointment, HimsError> {
         let mut tx = self.pool.begin().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
         // Update appointment status to cancelled
-        sqlx::query("UPDATE appointments SET status = 'cancelled', updated_at = NOW() WHERE id = $1")
-            .bind(&id.to_string())
+        sqlx::query(CANCEL_APPOINTMENT)
+            .bind(id.to_string())
             .execute(&mut *tx)
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
@@ -396,13 +696,14 @@ impl AppointmentService {
         self.create_audit_log(
             &mut tx,
             &id.to_string(),
-            "system",
+            auth,
             AuditEventType::Update,
             Some("Appointment cancelled".to_string()),
         ).await?;
 
         tx.commit().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.events.publish(DomainEvent::AppointmentCancelled { appointment_id: id });
 
         // Fetch and return the updated appointment
         self.get_appointment(&id.to_string()).await?
@@ -414,25 +715,19 @@ impl AppointmentService {
         &self,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         appointment_id: &str,
-        user_id: &str,
+        auth: &AuthContext,
         event_type: AuditEventType,
         details: Option<String>,
     ) -> Result<(), HimsError> {
-        let audit_log = AuditLog {
-            id: Uuid::new_v4().to_string(),
-            event_type,
-            user_id: user_id.to_string(),
-            patient_id: None,
-            appointment_id: Some(appointment_id.to_string()),
-            resource_type: AuditResourceType::Appointment,
-            resource_id: appointment_id.to_string(),
-            action: "CRUD".to_string(),
-            outcome: "Success".to_string(),
-            timestamp: Utc::now(),
-            source_ip: None,
-            user_agent: None,
-            details,
+        let action = match event_type {
+            AuditEventType::Create => AuditAction::Create,
+            AuditEventType::Delete => AuditAction::Delete,
+            _ => AuditAction::Update,
         };
+        let mut audit_log = auth.audit(AuditLog::new(event_type, action, "Appointment".to_string()));
+        audit_log.appointment_id = Some(appointment_id.to_string());
+        audit_log.resource_id = appointment_id.to_string();
+        audit_log.details = details;
 
         sqlx::query(CREATE_AUDIT_LOG)
             .bind(&audit_log.id)
@@ -448,10 +743,81 @@ impl AppointmentService {
             .bind(audit_log.source_ip.as_ref())
             .bind(audit_log.user_agent.as_ref())
             .bind(audit_log.details.as_ref())
+            .bind(audit_log.session_id.as_ref())
+            .bind(audit_log.purpose_of_use.as_ref())
             .execute(tx.as_mut())
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_search_from_params() {
+        let patient = Uuid::new_v4();
+        let reference = format!("Patient/{}", patient);
+        let search = AppointmentSearch::from_params(&params(&[
+            ("patient.name", "smith"),
+            ("patient.birthdate", "lt1960"),
+            ("actor:Patient", reference.as_str()),
+            ("status", "booked,arrived"),
+            ("_include", "Appointment:actor"),
+        ]))
+        .unwrap();
+
+        let chain = search.patient_chain.unwrap();
+        assert_eq!(chain.name.len(), 1);
+        assert_eq!(chain.birthdate.len(), 1);
+        assert_eq!(search.patient, vec![patient]);
+        assert_eq!(search.status, vec!["booked".to_string(), "arrived".to_string()]);
+        assert_eq!(search.include, vec![AppointmentInclude::Patient, AppointmentInclude::Practitioner]);
+
+        assert!(AppointmentSearch::from_params(&params(&[("practitioner.name", "jones")])).is_err());
+        assert!(AppointmentSearch::from_params(&params(&[("patient._count", "5")])).is_err());
+        assert!(AppointmentSearch::from_params(&params(&[("_include", "Appointment:location")])).is_err());
+        assert!(AppointmentSearch::from_params(&params(&[("status", "done")])).is_err());
+
+        let search = AppointmentSearch::from_params(&params(&[("_count", "500"), ("_total", "none")])).unwrap();
+        assert_eq!(search.page.count, MAX_SEARCH_COUNT);
+        assert_eq!(search.page.total(), TotalMode::None);
+        assert!(AppointmentSearch::from_params(&params(&[("_offset", "50")])).is_err());
+    }
+
+    #[test]
+    fn test_advertised_params_are_supported() {
+        let id = Uuid::new_v4();
+        for param in AppointmentSearch::SEARCH_PARAMS {
+            let (name, value) = match param.name {
+                "status" => ("status", "booked".to_string()),
+                "date" => ("date", "ge2024-01".to_string()),
+                "actor" => ("actor:Patient", id.to_string()),
+                name => (name, id.to_string()),
+            };
+            let search = AppointmentSearch::from_params(&params(&[(name, value.as_str())])).unwrap();
+            assert_ne!(search, AppointmentSearch::default(), "{} was ignored", param.name);
+        }
+        for include in AppointmentSearch::INCLUDES {
+            assert!(AppointmentSearch::from_params(&params(&[("_include", include)])).is_ok(), "{}", include);
+        }
+    }
+
+    #[test]
+    fn test_chained_search_sql() {
+        let search = AppointmentSearch::from_params(&params(&[("patient.name:exact", "Smith")])).unwrap();
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM appointments WHERE deleted_at IS NULL");
+        search.push_filters(&mut query);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM appointments WHERE deleted_at IS NULL AND patient_id IN \
+             (SELECT id FROM patients WHERE deleted_at IS NULL AND patient_name_parts(name) @> ARRAY[$1]::text[])"
+        );
+    }
+}
@@ -3,49 +3,131 @@
 pub const CREATE_APPOINTMENT: &str = r#"
     INSERT INTO appointments (
         id, patient_id, practitioner_id, start_time, end_time, 
-        status, service_type, comment, created_at, updated_at
-    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        status, service_type, comment, created_at, updated_at, meta
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
 "#;
 
 pub const GET_APPOINTMENT_BY_ID: &str = r#"
     SELECT id, patient_id, practitioner_id, start_time, end_time, 
-           status, service_type, comment, created_at, updated_at
+           status, service_type, comment, created_at, updated_at, meta
+    FROM appointments 
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+pub const GET_APPOINTMENT_VERSION: &str = r#"
+    SELECT COALESCE(meta->>'version_id', '1')
+    FROM appointments 
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+pub const GET_APPOINTMENT_STATUS: &str = r#"
+    SELECT status
     FROM appointments 
     WHERE id = $1 AND deleted_at IS NULL
 "#;
 
 pub const SEARCH_APPOINTMENTS: &str = r#"
     SELECT id, patient_id, practitioner_id, start_time, end_time, 
-           status, service_type, comment, created_at, updated_at
+           status, service_type, comment, created_at, updated_at, meta
     FROM appointments 
     WHERE deleted_at IS NULL
     ORDER BY start_time DESC
     LIMIT $1 OFFSET $2
 "#;
 
+/// Start of a FHIR appointment search. The service appends filters and the
+/// cursor, closes the `fetched` CTE with its order and a limit one past the
+/// page, then adds the `page` CTE holding just the page;
+/// SEARCH_APPOINTMENT_MATCHES and any includes then read from it, all in one
+/// round trip.
+pub const SEARCH_APPOINTMENTS_PAGE: &str = r#"
+    WITH fetched AS (
+        SELECT id, patient_id, practitioner_id, start_time, end_time,
+               status, service_type, comment, created_at, updated_at, meta
+        FROM appointments
+        WHERE deleted_at IS NULL
+"#;
+
+pub const SEARCH_APPOINTMENT_MATCHES: &str = r#"
+    SELECT 'match' AS search_mode, 'Appointment' AS resource_type, id::text AS id,
+           start_time, end_time, status, meta, NULL::jsonb AS resource,
+           ARRAY[start_time::text, id::text] AS page_key,
+           (SELECT COUNT(*) FROM fetched) AS fetched,
+           row_number() OVER (ORDER BY start_time DESC NULLS LAST, id) AS position
+    FROM page
+"#;
+
+pub const INCLUDE_APPOINTMENT_PATIENTS: &str = r#"
+    UNION ALL
+    SELECT 'include', 'Patient', p.id::text, NULL, NULL, NULL, NULL, to_jsonb(p), NULL, NULL, NULL
+    FROM patients p
+    WHERE p.id IN (SELECT patient_id FROM page)
+"#;
+
+pub const INCLUDE_APPOINTMENT_PRACTITIONERS: &str = r#"
+    UNION ALL
+    SELECT 'include', 'Practitioner', pr.id::text, NULL, NULL, NULL, NULL, to_jsonb(pr), NULL, NULL, NULL
+    FROM practitioners pr
+    WHERE pr.id IN (SELECT practitioner_id FROM page)
+"#;
+
+pub const COUNT_APPOINTMENTS: &str = r#"
+    SELECT COUNT(*) FROM appointments WHERE deleted_at IS NULL
+"#;
+
+/// Query plan of an appointment search, whose row estimate answers
+/// `_total=estimate`
+pub const ESTIMATE_APPOINTMENTS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM appointments WHERE deleted_at IS NULL
+"#;
+
+/// Patients of appointments $1, for access checks against them
+pub const GET_APPOINTMENT_PATIENTS: &str = r#"
+    SELECT id, patient_id
+    FROM appointments
+    WHERE id = ANY($1) AND patient_id IS NOT NULL
+"#;
+
 pub const UPDATE_APPOINTMENT_STATUS: &str = r#"
     UPDATE appointments 
-    SET status = $1, updated_at = NOW() 
+    SET status = $1, updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
     WHERE id = $2
 "#;
 
+pub const CANCEL_APPOINTMENT: &str = r#"
+    UPDATE appointments 
+    SET status = 'cancelled', updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1
+"#;
+
 pub const UPDATE_APPOINTMENT: &str = r#"
     UPDATE appointments 
     SET start_time = $1, end_time = $2, status = $3, patient_id = $4,
-        practitioner_id = $5, service_type = $6, comment = $7, updated_at = NOW()
-    WHERE id = $8
+        practitioner_id = $5, service_type = $6, comment = $7, updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($9::text))
+    WHERE id = $8 AND deleted_at IS NULL AND COALESCE(meta->>'version_id', '1') = $10
 "#;
 
 pub const DELETE_APPOINTMENT: &str = r#"
     UPDATE appointments 
-    SET deleted_at = NOW(), updated_at = NOW() 
+    SET deleted_at = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
     WHERE id = $1
 "#;
 
+/// Insert audit log entry; IDs are bound as text
 pub const CREATE_AUDIT_LOG: &str = r#"
     INSERT INTO audit_logs (
         id, event_type, user_id, patient_id, appointment_id,
         resource_type, resource_id, action, outcome, timestamp,
-        source_ip, user_agent, details
-    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        source_ip, user_agent, details, session_id, purpose_of_use
+    ) VALUES (
+        $1::uuid, $2, NULLIF($3, '')::uuid, $4::uuid, $5::uuid, $6, NULLIF($7, '')::uuid, $8, $9, $10,
+        $11::inet, $12, $13, $14, $15
+    )
 "#;
\ No newline at end of file
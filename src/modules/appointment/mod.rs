@@ -2,9 +2,12 @@
 //! 
 //! This module provides appointment management functionality including:
 //! - Appointment CRUD operations
+//! - FHIR search with chained patient criteria (`patient.name=smith`) and
+//!   `_include` of patients and practitioners
 //! - FHIR R4 compliance
 //! - Calendar integration support
 //! - Provider scheduling
+//! - Reads checked against the appointment's patient for the declared purpose of use
 //! - Audit logging
 
 #[path = "appointment.controller.rs"]
@@ -15,12 +18,15 @@ pub mod appointment_service;
 pub mod appointment_sql;
 
 pub use appointment_controller::AppointmentController;
-pub use appointment_service::AppointmentService;
+pub use appointment_service::{AppointmentInclude, AppointmentSearch, AppointmentSearchResult, AppointmentService};
 
-use axum::Router;
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::events::EventBus;
+use crate::utils::api_router::ApiRouter;
+
 /// Appointment Module Configuration
 pub struct AppointmentModule {
     pub service: Arc<AppointmentService>,
@@ -29,9 +35,9 @@ pub struct AppointmentModule {
 
 impl AppointmentModule {
     /// Create a new Appointment Module with dependency injection
-    pub fn new(db_pool: PgPool) -> Self {
-        let service = Arc::new(AppointmentService::new(db_pool));
-        let controller = Arc::new(AppointmentController::new(service.clone()));
+    pub fn new(db_pool: PgPool, events: EventBus, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        let service = Arc::new(AppointmentService::with_events(db_pool, events));
+        let controller = Arc::new(AppointmentController::new(service.clone(), authorization_engine));
         
         Self {
             service,
@@ -40,7 +46,7 @@ impl AppointmentModule {
     }
 
     /// Register routes for this module
-    pub fn routes(&self) -> Router {
+    pub fn routes(&self) -> ApiRouter {
         self.controller.routes()
     }
 
@@ -0,0 +1,342 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::attachment::attachment_service::{Attachment, DocumentReferenceContent, NewAttachment};
+use crate::modules::attachment::AttachmentService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::utils::api_router::ApiRouter;
+
+/// Attachment controller for binary documents on medical records. Access
+/// to attachments is access to their record: reading them takes read access
+/// to it, and adding or removing them takes update or delete access.
+pub struct AttachmentController {
+    attachment_service: Arc<AttachmentService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+}
+
+type AttachmentState = (Arc<AttachmentService>, Arc<HimsAuthorizationEngine>);
+
+#[derive(Debug, Deserialize)]
+pub struct AttachmentUploadQuery {
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl AttachmentController {
+    /// Create new controller with injected service
+    pub fn new(attachment_service: Arc<AttachmentService>, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        Self {
+            attachment_service,
+            authorization_engine,
+        }
+    }
+
+    /// Create router with dependency injection; mounted under the medical
+    /// records path
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/:id/attachments", Self::upload, "Attach a file to a medical record")
+            .get("/:id/attachments", Self::list, "List a medical record's attachments")
+            .get("/:id/attachments/:attachment_id", Self::get, "Get attachment details")
+            .delete("/:id/attachments/:attachment_id", Self::delete, "Remove an attachment from a medical record")
+            .get("/:id/attachments/:attachment_id/content", Self::download, "Download an attachment")
+            .get("/:id/content", Self::document_content, "Get a medical record's DocumentReference.content")
+            .with_state((self.attachment_service.clone(), self.authorization_engine.clone()))
+    }
+
+    /// Attach the request body, of the type in `Content-Type`, to a record
+    pub async fn upload(
+        State((service, authorization_engine)): State<AttachmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Query(query): Query<AttachmentUploadQuery>,
+        body: Body,
+    ) -> Result<(StatusCode, HeaderMap, Json<Attachment>), ErrorReply> {
+        Self::require(authorization_engine, &auth, &headers, Action::Update, id).await?;
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| {
+                Self::error(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "Content-Type required",
+                    "Send the file's type in Content-Type".to_string(),
+                )
+            })?;
+        let bytes = to_bytes(body, service.max_bytes()).await.map_err(|_| {
+            Self::error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "File too large",
+                format!("Attachments may be at most {} bytes", service.max_bytes()),
+            )
+        })?;
+        tracing::info!("Attaching {} bytes of {} to medical record {}", bytes.len(), content_type, id);
+
+        let file = NewAttachment {
+            content_type,
+            title: query.title.filter(|title| !title.trim().is_empty()),
+            bytes: &bytes,
+        };
+        match service.attach(id, file, &auth).await {
+            Ok(Some(attachment)) => {
+                let mut headers = HeaderMap::new();
+                if let Ok(location) = HeaderValue::from_str(&format!("/api/v1/medical-records/{}/attachments/{}", id, attachment.id)) {
+                    headers.insert(header::LOCATION, location);
+                }
+                Ok((StatusCode::CREATED, headers, Json(attachment)))
+            }
+            Ok(None) => Err(Self::record_not_found(id)),
+            Err(e) => {
+                tracing::error!("Failed to attach file to medical record {}: {}", id, e);
+                Err(Self::failure("Failed to attach file", e))
+            }
+        }
+    }
+
+    /// List a medical record's attachments
+    pub async fn list(
+        State((service, authorization_engine)): State<AttachmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<Attachment>>, ErrorReply> {
+        Self::require(authorization_engine, &auth, &headers, Action::Read, id).await?;
+        match service.list(id, &auth).await {
+            Ok(Some(attachments)) => Ok(Json(attachments)),
+            Ok(None) => Err(Self::record_not_found(id)),
+            Err(e) => {
+                tracing::error!("Failed to list attachments of medical record {}: {}", id, e);
+                Err(Self::failure("Failed to list attachments", e))
+            }
+        }
+    }
+
+    /// Get attachment details
+    pub async fn get(
+        State((service, authorization_engine)): State<AttachmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<Json<Attachment>, ErrorReply> {
+        Self::require(authorization_engine, &auth, &headers, Action::Read, id).await?;
+        match service.get(id, attachment_id, &auth).await {
+            Ok(Some(attachment)) => Ok(Json(attachment)),
+            Ok(None) => Err(Self::attachment_not_found(attachment_id)),
+            Err(e) => {
+                tracing::error!("Failed to retrieve attachment {}: {}", attachment_id, e);
+                Err(Self::failure("Failed to retrieve attachment", e))
+            }
+        }
+    }
+
+    /// Download an attachment's bytes. They are always sent as a download
+    /// with sniffing disabled, so a browser never renders them in the API's
+    /// origin.
+    pub async fn download(
+        State((service, authorization_engine)): State<AttachmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<Response, ErrorReply> {
+        Self::require(authorization_engine, &auth, &headers, Action::Read, id).await?;
+        match service.download(id, attachment_id, &auth).await {
+            Ok(Some((attachment, bytes))) => {
+                let content_type = HeaderValue::from_str(&attachment.content_type)
+                    .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+                let etag = HeaderValue::from_str(&format!("\"{}\"", attachment.sha256))
+                    .unwrap_or(HeaderValue::from_static("\"\""));
+                Ok((
+                    [
+                        (header::CONTENT_TYPE, content_type),
+                        (header::ETAG, etag),
+                        (header::CONTENT_DISPOSITION, HeaderValue::from_static("attachment")),
+                        (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                        (header::CACHE_CONTROL, HeaderValue::from_static("private, no-store")),
+                    ],
+                    bytes,
+                )
+                    .into_response())
+            }
+            Ok(None) => Err(Self::attachment_not_found(attachment_id)),
+            Err(e) => {
+                tracing::error!("Failed to download attachment {}: {}", attachment_id, e);
+                Err(Self::failure("Failed to download attachment", e))
+            }
+        }
+    }
+
+    /// Remove an attachment from a medical record that is not finalized
+    pub async fn delete(
+        State((service, authorization_engine)): State<AttachmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<StatusCode, ErrorReply> {
+        Self::require(authorization_engine, &auth, &headers, Action::Delete, id).await?;
+        match service.delete(id, attachment_id, &auth).await {
+            Ok(true) => {
+                tracing::info!("Attachment {} removed from medical record {}", attachment_id, id);
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err(Self::attachment_not_found(attachment_id)),
+            Err(e) => {
+                tracing::error!("Failed to remove attachment {}: {}", attachment_id, e);
+                Err(Self::failure("Failed to remove attachment", e))
+            }
+        }
+    }
+
+    /// A medical record's attachments as FHIR `DocumentReference.content`
+    pub async fn document_content(
+        State((service, authorization_engine)): State<AttachmentState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<DocumentReferenceContent>>, ErrorReply> {
+        Self::require(authorization_engine, &auth, &headers, Action::Read, id).await?;
+        match service.list(id, &auth).await {
+            Ok(Some(attachments)) => Ok(Json(attachments.iter().map(Attachment::to_document_content).collect())),
+            Ok(None) => Err(Self::record_not_found(id)),
+            Err(e) => {
+                tracing::error!("Failed to list attachments of medical record {}: {}", id, e);
+                Err(Self::failure("Failed to list attachments", e))
+            }
+        }
+    }
+
+    /// Fail with 403 unless the user may take `action` on the medical record
+    async fn require(
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        action: Action,
+        id: Uuid,
+    ) -> Result<(), ErrorReply> {
+        let result = match FieldAuthorizer::for_headers(authorization_engine, auth, headers).await {
+            Ok(authorizer) => authorizer.authorize(action, Resource::MedicalRecord(id)).await,
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| match e {
+            HimsError::SecurityError { message } => {
+                tracing::warn!("Access to attachments of medical record {} denied: {}", id, message);
+                Self::error(StatusCode::FORBIDDEN, "Access denied", message)
+            }
+            e => {
+                tracing::error!("Authorization check failed: {}", e);
+                Self::error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Authorization error",
+                    "Failed to check authorization".to_string(),
+                )
+            }
+        })
+    }
+
+    fn error(status: StatusCode, error: &str, message: String) -> ErrorReply {
+        (
+            status,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message,
+            }),
+        )
+    }
+
+    /// Status for a service error: bad uploads are the client's, infected
+    /// files unprocessable, changes to finalized records conflicts, and an
+    /// unreachable store or scanner unavailable
+    fn failure(error: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            HimsError::SecurityError { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            HimsError::NetworkError { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::error(status, error, e.to_string())
+    }
+
+    fn record_not_found(id: Uuid) -> ErrorReply {
+        Self::error(
+            StatusCode::NOT_FOUND,
+            "Medical record not found",
+            format!("Medical record with id {} not found", id),
+        )
+    }
+
+    fn attachment_not_found(id: Uuid) -> ErrorReply {
+        Self::error(
+            StatusCode::NOT_FOUND,
+            "Attachment not found",
+            format!("Attachment with id {} not found", id),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use crate::modules::attachment::LocalStore;
+    use crate::modules::auth::auth_jwt::test_tokens;
+    use crate::modules::auth::AuthenticatedUser;
+    use crate::modules::authorization::{AuthorizationConfig, StorageBackend};
+
+    #[tokio::test]
+    async fn test_attachments_need_record_access() {
+        // Never connected: the engine turns requests away before any lookup
+        let pool = sqlx::PgPool::connect_lazy("postgres://localhost/hims").unwrap();
+        let store = Arc::new(LocalStore::new(std::env::temp_dir().join("hims-attachment-test")));
+        let service = AttachmentService::new(pool, store, None, 1024);
+        let config = AuthorizationConfig {
+            storage_backend: StorageBackend::InMemory,
+            ..AuthorizationConfig::default()
+        };
+        let engine = HimsAuthorizationEngine::from_config(config, None).await.unwrap();
+        let mut router = AttachmentController::new(Arc::new(service), Arc::new(engine)).routes().into_router();
+        let tokens = test_tokens(&AuthenticatedUser {
+            id: Uuid::new_v4().to_string(),
+            username: "clinician".to_string(),
+            role: "physician".to_string(),
+            permissions: vec![],
+        });
+
+        let (id, attachment_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let requests = [
+            Request::post(format!("/{}/attachments", id)).body(Body::from("%PDF-1.7\n")),
+            Request::get(format!("/{}/attachments", id)).body(Body::empty()),
+            Request::get(format!("/{}/attachments/{}", id, attachment_id)).body(Body::empty()),
+            Request::get(format!("/{}/attachments/{}/content", id, attachment_id)).body(Body::empty()),
+            Request::delete(format!("/{}/attachments/{}", id, attachment_id)).body(Body::empty()),
+            Request::get(format!("/{}/content", id)).body(Body::empty()),
+        ];
+        for request in requests {
+            let mut request = request.unwrap();
+            let headers = request.headers_mut();
+            headers.insert("authorization", format!("Bearer {}", tokens.access_token).parse().unwrap());
+            headers.insert("x-purpose-of-use", "treatment".parse().unwrap());
+            headers.insert("content-type", "application/pdf".parse().unwrap());
+            let uri = request.uri().clone();
+            let response = tower::Service::call(&mut router, request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", uri);
+        }
+    }
+}
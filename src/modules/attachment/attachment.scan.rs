@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::core::HimsError;
+
+/// What a scanner made of an upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// Malware was found; the signature it matched
+    Infected(String),
+}
+
+/// Malware scanning of uploads before they are stored
+#[async_trait]
+pub trait VirusScanner: Send + Sync {
+    async fn scan(&self, bytes: &[u8]) -> Result<ScanVerdict, HimsError>;
+}
+
+/// ClamAV's clamd over TCP, streaming uploads with `INSTREAM`
+#[derive(Debug, Clone)]
+pub struct ClamdScanner {
+    address: String,
+    timeout: Duration,
+}
+
+impl ClamdScanner {
+    /// Chunk size sent to clamd; its StreamMaxLength still applies overall
+    const CHUNK: usize = 64 * 1024;
+
+    /// Scanner for clamd at `host:port`
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    async fn instream(&self, bytes: &[u8]) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(&self.address).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in bytes.chunks(Self::CHUNK) {
+            stream.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok(String::from_utf8_lossy(&reply).trim_end_matches('\0').trim().to_string())
+    }
+
+    /// Verdict from a clamd reply such as `stream: OK` or
+    /// `stream: Eicar-Signature FOUND`
+    pub fn verdict(reply: &str) -> Result<ScanVerdict, HimsError> {
+        let result = reply.strip_prefix("stream:").map(str::trim).unwrap_or(reply);
+        if result == "OK" {
+            Ok(ScanVerdict::Clean)
+        } else if let Some(signature) = result.strip_suffix("FOUND") {
+            Ok(ScanVerdict::Infected(signature.trim().to_string()))
+        } else {
+            Err(HimsError::InternalError {
+                message: format!("Virus scan failed: {}", reply),
+            })
+        }
+    }
+}
+
+#[async_trait]
+impl VirusScanner for ClamdScanner {
+    async fn scan(&self, bytes: &[u8]) -> Result<ScanVerdict, HimsError> {
+        let reply = tokio::time::timeout(self.timeout, self.instream(bytes))
+            .await
+            .map_err(|_| HimsError::NetworkError {
+                message: format!("Virus scanner at {} timed out", self.address),
+            })?
+            .map_err(|e| HimsError::NetworkError {
+                message: format!("Virus scanner at {} unavailable: {}", self.address, e),
+            })?;
+        Self::verdict(&reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamd_verdict() {
+        assert_eq!(ClamdScanner::verdict("stream: OK").unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            ClamdScanner::verdict("stream: Win.Test.EICAR_HDB-1 FOUND").unwrap(),
+            ScanVerdict::Infected("Win.Test.EICAR_HDB-1".to_string())
+        );
+        assert!(ClamdScanner::verdict("INSTREAM size limit exceeded. ERROR").is_err());
+    }
+}
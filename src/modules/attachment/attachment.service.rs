@@ -0,0 +1,572 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::current_tenant_id;
+use crate::models::{AuditAction, AuditEventType, AuditLog};
+use crate::modules::attachment::attachment_scan::{ClamdScanner, ScanVerdict, VirusScanner};
+use crate::modules::attachment::attachment_store::{AttachmentStore, LocalStore, S3Config, S3Store};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+
+// Import SQL queries from separate file
+use crate::modules::attachment::attachment_sql::*;
+
+/// Whether bytes really are of a content type
+type ContentCheck = fn(&[u8]) -> bool;
+
+/// Content types accepted, each with a check that the bytes really are of
+/// that type, so a script cannot be uploaded as an image
+const ACCEPTED_TYPES: [(&str, ContentCheck); 5] = [
+    ("application/pdf", |bytes| bytes.starts_with(b"%PDF-")),
+    ("image/png", |bytes| bytes.starts_with(b"\x89PNG\r\n\x1a\n")),
+    ("image/jpeg", |bytes| bytes.starts_with(&[0xFF, 0xD8, 0xFF])),
+    ("image/tiff", |bytes| bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*")),
+    // Part 10 files: a 128-byte preamble, then the DICM prefix
+    ("application/dicom", |bytes| bytes.get(128..132) == Some(&b"DICM"[..])),
+];
+
+/// Attachment settings
+#[derive(Clone)]
+pub struct AttachmentConfig {
+    /// Largest file accepted
+    pub max_bytes: usize,
+    /// Directory files are kept in when no S3 bucket is configured
+    pub directory: PathBuf,
+    pub s3: Option<S3Config>,
+    /// clamd `host:port`; uploads are stored unscanned without one
+    pub clamd_address: Option<String>,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 25 * 1024 * 1024,
+            directory: PathBuf::from("data/attachments"),
+            s3: None,
+            clamd_address: None,
+        }
+    }
+}
+
+impl AttachmentConfig {
+    /// Settings from `ATTACHMENT_*` variables. Files go to S3 when
+    /// `ATTACHMENT_S3_BUCKET` is set, otherwise to `ATTACHMENT_DIR`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        let s3 = var("ATTACHMENT_S3_BUCKET").map(|bucket| S3Config {
+            endpoint: var("ATTACHMENT_S3_ENDPOINT").unwrap_or_else(|| "https://s3.amazonaws.com".to_string()),
+            bucket,
+            region: var("ATTACHMENT_S3_REGION").unwrap_or_else(|| "us-east-1".to_string()),
+            access_key_id: var("ATTACHMENT_S3_ACCESS_KEY_ID").unwrap_or_default(),
+            secret_access_key: var("ATTACHMENT_S3_SECRET_ACCESS_KEY").unwrap_or_default(),
+        });
+
+        Self {
+            max_bytes: var("ATTACHMENT_MAX_BYTES")
+                .and_then(|max| max.parse().ok())
+                .unwrap_or(defaults.max_bytes),
+            directory: var("ATTACHMENT_DIR").map(PathBuf::from).unwrap_or(defaults.directory),
+            s3,
+            clamd_address: var("ATTACHMENT_CLAMD_ADDRESS"),
+        }
+    }
+
+    /// Store for the configured backend
+    pub fn store(&self) -> Result<Arc<dyn AttachmentStore>, HimsError> {
+        match &self.s3 {
+            Some(s3) => Ok(Arc::new(S3Store::new(s3.clone())?)),
+            None => Ok(Arc::new(LocalStore::new(self.directory.clone()))),
+        }
+    }
+
+    pub fn scanner(&self) -> Option<Arc<dyn VirusScanner>> {
+        self.clamd_address
+            .as_ref()
+            .map(|address| Arc::new(ClamdScanner::new(address.clone())) as Arc<dyn VirusScanner>)
+    }
+}
+
+/// Whether an attachment was checked for malware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScanStatus {
+    Clean,
+    /// No scanner was configured when it was uploaded
+    NotScanned,
+}
+
+impl ScanStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanStatus::Clean => "clean",
+            ScanStatus::NotScanned => "not-scanned",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "clean" => ScanStatus::Clean,
+            _ => ScanStatus::NotScanned,
+        }
+    }
+}
+
+/// A file attached to a medical record
+#[derive(Debug, Clone, Serialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub medical_record_id: Uuid,
+    pub title: Option<String>,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Hex SHA-256 of the bytes
+    pub sha256: String,
+    /// Base64 SHA-1 of the bytes, as FHIR carries it
+    pub sha1: String,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub scan_status: ScanStatus,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// FHIR `Attachment`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FhirAttachment {
+    pub content_type: String,
+    /// Where the bytes are downloaded, relative to the API root
+    pub url: String,
+    pub size: i64,
+    pub hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub creation: DateTime<Utc>,
+}
+
+/// FHIR `DocumentReference.content`
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentReferenceContent {
+    pub attachment: FhirAttachment,
+}
+
+impl Attachment {
+    /// Path the bytes are downloaded from
+    pub fn content_url(&self) -> String {
+        format!("medical-records/{}/attachments/{}/content", self.medical_record_id, self.id)
+    }
+
+    /// This attachment as an entry of its record's `DocumentReference.content`
+    pub fn to_document_content(&self) -> DocumentReferenceContent {
+        DocumentReferenceContent {
+            attachment: FhirAttachment {
+                content_type: self.content_type.clone(),
+                url: self.content_url(),
+                size: self.size_bytes,
+                hash: self.sha1.clone(),
+                title: self.title.clone(),
+                creation: self.created_at,
+            },
+        }
+    }
+
+    fn from_row(row: &PgRow) -> Self {
+        Self {
+            id: row.get("id"),
+            medical_record_id: row.get("medical_record_id"),
+            title: row.get("title"),
+            content_type: row.get("content_type"),
+            size_bytes: row.get("size_bytes"),
+            sha256: row.get("sha256"),
+            sha1: row.get("sha1"),
+            storage_key: row.get("storage_key"),
+            scan_status: ScanStatus::from_string(&row.get::<String, _>("scan_status")),
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+/// A file to attach
+#[derive(Debug, Clone)]
+pub struct NewAttachment<'a> {
+    /// As sent, parameters such as `charset` included
+    pub content_type: &'a str,
+    pub title: Option<String>,
+    pub bytes: &'a [u8],
+}
+
+/// Whether a medical record's status means it is signed and can no
+/// longer change
+fn is_finalized(status: &str) -> bool {
+    status == "final" || status == "amended"
+}
+
+/// Media type of a `Content-Type` value, without parameters
+pub fn media_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+/// The media type of an upload, if it is an accepted type and the bytes
+/// are of that type
+pub fn check_content(content_type: &str, bytes: &[u8]) -> Result<String, HimsError> {
+    let media_type = media_type(content_type);
+    let Some((_, matches)) = ACCEPTED_TYPES.iter().find(|(accepted, _)| *accepted == media_type) else {
+        let accepted: Vec<&str> = ACCEPTED_TYPES.iter().map(|(accepted, _)| *accepted).collect();
+        return Err(HimsError::ValidationError {
+            message: format!("Content type {:?} is not accepted; use one of {}", media_type, accepted.join(", ")),
+        });
+    };
+    if !matches(bytes) {
+        return Err(HimsError::ValidationError {
+            message: format!("File contents are not {}", media_type),
+        });
+    }
+    Ok(media_type)
+}
+
+/// Hex SHA-256 and base64 SHA-1 of `bytes`
+pub fn checksums(bytes: &[u8]) -> (String, String) {
+    let sha256 = Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect();
+    let sha1 = ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, bytes);
+    (sha256, general_purpose::STANDARD.encode(sha1.as_ref()))
+}
+
+/// Binary documents attached to medical records: validated, scanned,
+/// checksummed and kept in the attachment store
+pub struct AttachmentService {
+    pool: PgPool,
+    store: Arc<dyn AttachmentStore>,
+    scanner: Option<Arc<dyn VirusScanner>>,
+    max_bytes: usize,
+    audit_service: AuditService,
+}
+
+impl AttachmentService {
+    pub fn new(
+        pool: PgPool,
+        store: Arc<dyn AttachmentStore>,
+        scanner: Option<Arc<dyn VirusScanner>>,
+        max_bytes: usize,
+    ) -> Self {
+        Self {
+            audit_service: AuditService::new(pool.clone()),
+            pool,
+            store,
+            scanner,
+            max_bytes,
+        }
+    }
+
+    pub fn from_config(pool: PgPool, config: AttachmentConfig) -> Self {
+        let store = config.store().unwrap_or_else(|e| {
+            tracing::error!("Attachments fall back to {}: {}", config.directory.display(), e);
+            Arc::new(LocalStore::new(config.directory.clone()))
+        });
+        if config.clamd_address.is_none() {
+            tracing::warn!("No virus scanner configured; attachments are stored unscanned");
+        }
+        Self::new(pool, store, config.scanner(), config.max_bytes)
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Patient of a medical record that is not deleted, and its status
+    async fn record(&self, medical_record_id: Uuid) -> Result<Option<(Uuid, String)>, HimsError> {
+        let row = sqlx::query(GET_MEDICAL_RECORD)
+            .bind(medical_record_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(row.map(|row| (row.get("patient_id"), row.get("status"))))
+    }
+
+    /// Record an access to or change of a medical record's attachments
+    async fn audit(
+        &self,
+        auth: &AuthContext,
+        (event_type, action): (AuditEventType, AuditAction),
+        patient_id: Uuid,
+        resource_id: Uuid,
+        details: String,
+    ) -> Result<(), HimsError> {
+        let audit_log = auth
+            .audit(AuditLog::new(event_type, action, "Attachment".to_string()))
+            .with_patient(patient_id)
+            .with_resource(resource_id)
+            .with_details(details);
+        self.audit_service.create_audit_log(&audit_log).await?;
+        Ok(())
+    }
+
+    /// Attach a file to a medical record. `None` if there is no such record.
+    pub async fn attach(
+        &self,
+        medical_record_id: Uuid,
+        file: NewAttachment<'_>,
+        auth: &AuthContext,
+    ) -> Result<Option<Attachment>, HimsError> {
+        if file.bytes.is_empty() || file.bytes.len() > self.max_bytes {
+            return Err(HimsError::ValidationError {
+                message: format!("Attachments must be 1 to {} bytes", self.max_bytes),
+            });
+        }
+        let content_type = check_content(file.content_type, file.bytes)?;
+
+        let Some((patient_id, _)) = self.record(medical_record_id).await? else {
+            return Ok(None);
+        };
+
+        // Scanner failures reject the upload rather than store it unchecked
+        let scan_status = match &self.scanner {
+            Some(scanner) => match scanner.scan(file.bytes).await? {
+                ScanVerdict::Clean => ScanStatus::Clean,
+                ScanVerdict::Infected(signature) => {
+                    tracing::warn!("Rejected attachment for medical record {}: {}", medical_record_id, signature);
+                    return Err(HimsError::SecurityError {
+                        message: format!("File rejected by virus scan: {}", signature),
+                    });
+                }
+            },
+            None => ScanStatus::NotScanned,
+        };
+
+        let id = Uuid::new_v4();
+        let storage_key = format!("{}/{}/{}", current_tenant_id(), medical_record_id, id);
+        let (sha256, sha1) = checksums(file.bytes);
+        self.store.put(&storage_key, file.bytes, &content_type).await?;
+
+        let row = sqlx::query(INSERT_ATTACHMENT)
+            .bind(id)
+            .bind(medical_record_id)
+            .bind(&file.title)
+            .bind(&content_type)
+            .bind(file.bytes.len() as i64)
+            .bind(&sha256)
+            .bind(&sha1)
+            .bind(&storage_key)
+            .bind(scan_status.as_str())
+            .bind(auth.user_id)
+            .fetch_one(&self.pool)
+            .await;
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                if let Err(cleanup) = self.store.delete(&storage_key).await {
+                    tracing::error!("Failed to remove unrecorded attachment {}: {}", storage_key, cleanup);
+                }
+                return Err(HimsError::DatabaseError(e.to_string()));
+            }
+        };
+
+        tracing::info!("Attached {} ({} bytes) to medical record {}", content_type, file.bytes.len(), medical_record_id);
+        let attachment = Attachment::from_row(&row);
+        self.audit(
+            auth,
+            (AuditEventType::Create, AuditAction::Create),
+            patient_id,
+            attachment.id,
+            format!("Attached to medical record {}", medical_record_id),
+        )
+        .await?;
+        Ok(Some(attachment))
+    }
+
+    /// A medical record's attachments, oldest first. `None` if there is no
+    /// such record.
+    pub async fn list(&self, medical_record_id: Uuid, auth: &AuthContext) -> Result<Option<Vec<Attachment>>, HimsError> {
+        let Some((patient_id, _)) = self.record(medical_record_id).await? else {
+            return Ok(None);
+        };
+        let rows = sqlx::query(LIST_ATTACHMENTS)
+            .bind(medical_record_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let attachments: Vec<Attachment> = rows.iter().map(Attachment::from_row).collect();
+
+        self.audit(
+            auth,
+            (AuditEventType::Access, AuditAction::Read),
+            patient_id,
+            medical_record_id,
+            format!("{} attachments of medical record listed", attachments.len()),
+        )
+        .await?;
+        Ok(Some(attachments))
+    }
+
+    /// An attachment with the patient of its record
+    async fn find(&self, medical_record_id: Uuid, id: Uuid) -> Result<Option<(Uuid, Attachment)>, HimsError> {
+        let Some((patient_id, _)) = self.record(medical_record_id).await? else {
+            return Ok(None);
+        };
+        let row = sqlx::query(GET_ATTACHMENT)
+            .bind(id)
+            .bind(medical_record_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(row.map(|row| (patient_id, Attachment::from_row(&row))))
+    }
+
+    pub async fn get(&self, medical_record_id: Uuid, id: Uuid, auth: &AuthContext) -> Result<Option<Attachment>, HimsError> {
+        let Some((patient_id, attachment)) = self.find(medical_record_id, id).await? else {
+            return Ok(None);
+        };
+        self.audit(
+            auth,
+            (AuditEventType::Access, AuditAction::Read),
+            patient_id,
+            id,
+            format!("Attachment details of medical record {}", medical_record_id),
+        )
+        .await?;
+        Ok(Some(attachment))
+    }
+
+    /// An attachment with its bytes, which must still match the checksum
+    /// taken at upload
+    pub async fn download(
+        &self,
+        medical_record_id: Uuid,
+        id: Uuid,
+        auth: &AuthContext,
+    ) -> Result<Option<(Attachment, Vec<u8>)>, HimsError> {
+        let Some((patient_id, attachment)) = self.find(medical_record_id, id).await? else {
+            return Ok(None);
+        };
+        let bytes = self.store.get(&attachment.storage_key).await?.ok_or_else(|| HimsError::InternalError {
+            message: format!("Attachment {} is missing from storage", id),
+        })?;
+
+        let (sha256, _) = checksums(&bytes);
+        if sha256 != attachment.sha256 {
+            tracing::error!("Attachment {} does not match its checksum; refusing to serve it", id);
+            return Err(HimsError::InternalError {
+                message: format!("Attachment {} is corrupt", id),
+            });
+        }
+
+        self.audit(
+            auth,
+            (AuditEventType::Access, AuditAction::Read),
+            patient_id,
+            id,
+            format!("Attachment of medical record {} downloaded", medical_record_id),
+        )
+        .await?;
+        Ok(Some((attachment, bytes)))
+    }
+
+    /// Remove an attachment from its record. The bytes are kept, as
+    /// clinical documents are retained with the record. Attachments of
+    /// finalized records are part of what was signed and cannot be removed.
+    pub async fn delete(&self, medical_record_id: Uuid, id: Uuid, auth: &AuthContext) -> Result<bool, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let record = sqlx::query(LOCK_MEDICAL_RECORD)
+            .bind(medical_record_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let Some(record) = record else {
+            return Ok(false);
+        };
+        let status: String = record.get("status");
+        if is_finalized(&status) {
+            return Err(HimsError::ConflictError {
+                message: format!("Medical record {} is {}; its attachments cannot be removed", medical_record_id, status),
+            });
+        }
+
+        let result = sqlx::query(DELETE_ATTACHMENT)
+            .bind(id)
+            .bind(medical_record_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        tx.commit().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        self.audit(
+            auth,
+            (AuditEventType::Delete, AuditAction::Delete),
+            record.get("patient_id"),
+            id,
+            format!("Removed from medical record {}", medical_record_id),
+        )
+        .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_content() {
+        assert_eq!(check_content("application/pdf", b"%PDF-1.7\n").unwrap(), "application/pdf");
+        assert_eq!(check_content("Image/PNG; charset=binary", b"\x89PNG\r\n\x1a\n....").unwrap(), "image/png");
+        assert!(check_content("image/jpeg", b"%PDF-1.7\n").is_err());
+        assert!(check_content("text/html", b"<script>").is_err());
+
+        let mut dicom = vec![0u8; 128];
+        dicom.extend_from_slice(b"DICM");
+        assert!(check_content("application/dicom", &dicom).is_ok());
+        assert!(check_content("application/dicom", &dicom[..130]).is_err());
+    }
+
+    #[test]
+    fn test_is_finalized() {
+        assert!(is_finalized("final"));
+        assert!(is_finalized("amended"));
+        assert!(!is_finalized("preliminary"));
+        assert!(!is_finalized("entered-in-error"));
+    }
+
+    #[test]
+    fn test_checksums() {
+        let (sha256, sha1) = checksums(b"abc");
+        assert_eq!(sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(sha1, "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=");
+    }
+
+    #[test]
+    fn test_document_content() {
+        let attachment = Attachment {
+            id: Uuid::new_v4(),
+            medical_record_id: Uuid::new_v4(),
+            title: Some("Consent form".to_string()),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 1024,
+            sha256: String::new(),
+            sha1: "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=".to_string(),
+            storage_key: "secret/path".to_string(),
+            scan_status: ScanStatus::Clean,
+            created_by: None,
+            created_at: Utc::now(),
+        };
+
+        let content = serde_json::to_value(attachment.to_document_content()).unwrap();
+        assert_eq!(content["attachment"]["contentType"], "application/pdf");
+        assert_eq!(content["attachment"]["hash"], "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=");
+        assert_eq!(content["attachment"]["size"], 1024);
+        assert!(content["attachment"]["url"].as_str().unwrap().ends_with("/content"));
+        assert!(serde_json::to_value(&attachment).unwrap().get("storage_key").is_none());
+    }
+}
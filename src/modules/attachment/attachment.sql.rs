@@ -0,0 +1,52 @@
+//! Attachment SQL Queries
+//!
+//! This file contains all SQL queries used by the attachment service
+//! for clean separation of concerns and better maintainability.
+
+/// Patient and status of a medical record that is not deleted
+pub const GET_MEDICAL_RECORD: &str = r#"
+    SELECT patient_id, status FROM medical_records WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// [`GET_MEDICAL_RECORD`], locking the record against finalization for the
+/// rest of the transaction
+pub const LOCK_MEDICAL_RECORD: &str = r#"
+    SELECT patient_id, status FROM medical_records WHERE id = $1 AND deleted_at IS NULL
+    FOR SHARE
+"#;
+
+/// Record an attachment whose bytes have been stored
+pub const INSERT_ATTACHMENT: &str = r#"
+    INSERT INTO medical_record_attachments (
+        id, medical_record_id, title, content_type, size_bytes, sha256, sha1,
+        storage_key, scan_status, created_by
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10
+    )
+    RETURNING id, medical_record_id, title, content_type, size_bytes, sha256, sha1,
+              storage_key, scan_status, created_by, created_at
+"#;
+
+/// Get an attachment of a medical record
+pub const GET_ATTACHMENT: &str = r#"
+    SELECT id, medical_record_id, title, content_type, size_bytes, sha256, sha1,
+           storage_key, scan_status, created_by, created_at
+    FROM medical_record_attachments
+    WHERE id = $1 AND medical_record_id = $2 AND deleted_at IS NULL
+"#;
+
+/// List a medical record's attachments, oldest first
+pub const LIST_ATTACHMENTS: &str = r#"
+    SELECT id, medical_record_id, title, content_type, size_bytes, sha256, sha1,
+           storage_key, scan_status, created_by, created_at
+    FROM medical_record_attachments
+    WHERE medical_record_id = $1 AND deleted_at IS NULL
+    ORDER BY created_at, id
+"#;
+
+/// Soft delete an attachment; its bytes are kept with the record
+pub const DELETE_ATTACHMENT: &str = r#"
+    UPDATE medical_record_attachments
+    SET deleted_at = NOW()
+    WHERE id = $1 AND medical_record_id = $2 AND deleted_at IS NULL
+"#;
@@ -0,0 +1,295 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+
+use crate::core::HimsError;
+
+/// Where attachment bytes are kept, under keys the attachment service
+/// chooses. Keys are `/`-separated and never contain `..`.
+#[async_trait]
+pub trait AttachmentStore: Send + Sync {
+    async fn put(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), HimsError>;
+
+    /// Bytes stored under `key`, or `None` if there are none
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, HimsError>;
+
+    async fn delete(&self, key: &str) -> Result<(), HimsError>;
+}
+
+/// Attachments as files under a directory on this machine, or a volume
+/// every instance mounts
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, HimsError> {
+        let relative = Path::new(key);
+        let safe = !key.is_empty() && relative.components().all(|component| matches!(component, Component::Normal(_)));
+        if !safe {
+            return Err(HimsError::ValidationError {
+                message: format!("Invalid attachment storage key: {}", key),
+            });
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for LocalStore {
+    async fn put(&self, key: &str, bytes: &[u8], _content_type: &str) -> Result<(), HimsError> {
+        let path = self.path(key)?;
+        let io_error = |e: std::io::Error| HimsError::InternalError {
+            message: format!("Failed to write attachment {}: {}", key, e),
+        };
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+        // Written aside and renamed, so a reader never sees half a file
+        let partial = path.with_extension("part");
+        tokio::fs::write(&partial, bytes).await.map_err(io_error)?;
+        tokio::fs::rename(&partial, &path).await.map_err(io_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, HimsError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(HimsError::InternalError {
+                message: format!("Failed to read attachment {}: {}", key, e),
+            }),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), HimsError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(HimsError::InternalError {
+                message: format!("Failed to delete attachment {}: {}", key, e),
+            }),
+        }
+    }
+}
+
+/// Credentials and location of an S3-compatible bucket. Not `Debug`, so the
+/// secret cannot end up in logs.
+#[derive(Clone)]
+pub struct S3Config {
+    /// e.g. `https://s3.eu-west-1.amazonaws.com` or `http://minio:9000`
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+/// Attachments as objects in an S3-compatible bucket, addressed path-style
+/// (`{endpoint}/{bucket}/{key}`) so MinIO and other stores work unchanged.
+/// Requests are signed with AWS Signature Version 4.
+pub struct S3Store {
+    config: S3Config,
+    client: reqwest::Client,
+}
+
+impl S3Store {
+    pub fn new(config: S3Config) -> Result<Self, HimsError> {
+        let endpoint = reqwest::Url::parse(&config.endpoint).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Invalid attachment S3 endpoint {}: {}", config.endpoint, e),
+        })?;
+        if endpoint.host_str().is_none() || config.bucket.is_empty() {
+            return Err(HimsError::ConfigurationError {
+                message: "Attachment S3 storage needs an endpoint host and a bucket".to_string(),
+            });
+        }
+
+        Ok(Self {
+            config: S3Config {
+                endpoint: config.endpoint.trim_end_matches('/').to_string(),
+                ..config
+            },
+            client: reqwest::Client::new(),
+        })
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Option<(&[u8], &str)>,
+    ) -> Result<reqwest::Response, HimsError> {
+        let path = format!("/{}/{}", uri_encode(&self.config.bucket, false), uri_encode(key, true));
+        let url = format!("{}{}", self.config.endpoint, path);
+        let host = reqwest::Url::parse(&url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| (host.to_string(), url.port())))
+            .map(|(host, port)| port.map_or(host.clone(), |port| format!("{}:{}", host, port)))
+            .unwrap_or_default();
+        let payload = body.map_or(&[][..], |(bytes, _)| bytes);
+        let payload_hash = hex(&Sha256::digest(payload));
+        let now = Utc::now();
+
+        let authorization = sigv4_authorization(&self.config, method.as_str(), &path, &host, &payload_hash, now);
+        let mut request = self
+            .client
+            .request(method, &url)
+            .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("authorization", authorization);
+        if let Some((bytes, content_type)) = body {
+            request = request.header("content-type", content_type).body(bytes.to_vec());
+        }
+
+        request.send().await.map_err(|e| HimsError::NetworkError {
+            message: format!("Attachment storage request failed: {}", e),
+        })
+    }
+
+    fn failure(action: &str, key: &str, status: reqwest::StatusCode) -> HimsError {
+        HimsError::NetworkError {
+            message: format!("Attachment storage {} of {} failed with {}", action, key, status),
+        }
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for S3Store {
+    async fn put(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), HimsError> {
+        let response = self.send(reqwest::Method::PUT, key, Some((bytes, content_type))).await?;
+        if !response.status().is_success() {
+            return Err(Self::failure("upload", key, response.status()));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, HimsError> {
+        let response = self.send(reqwest::Method::GET, key, None).await?;
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let bytes = response.bytes().await.map_err(|e| HimsError::NetworkError {
+                    message: format!("Failed to read attachment {} from storage: {}", key, e),
+                })?;
+                Ok(Some(bytes.to_vec()))
+            }
+            status => Err(Self::failure("download", key, status)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), HimsError> {
+        let response = self.send(reqwest::Method::DELETE, key, None).await?;
+        // Deleting a missing object succeeds on S3 but is a 404 on some stores
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(Self::failure("delete", key, response.status()));
+        }
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent-encode as SigV4 canonical URIs require: everything but
+/// unreserved characters, and `/` too unless `keep_slash`
+fn uri_encode(value: &str, keep_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// SigV4 signing key for a day, region and service
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> hmac::Tag {
+    let sign = |key: &[u8], data: &str| hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes());
+    let key = sign(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let key = sign(key.as_ref(), region);
+    let key = sign(key.as_ref(), service);
+    sign(key.as_ref(), "aws4_request")
+}
+
+/// `Authorization` header for a request without a query string, signing
+/// `host`, `x-amz-content-sha256` and `x-amz-date`
+fn sigv4_authorization(
+    config: &S3Config,
+    method: &str,
+    canonical_uri: &str,
+    host: &str,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method, canonical_uri, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = signing_key(&config.secret_access_key, &date, &config.region, "s3");
+    let signature = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key.as_ref()), string_to_sign.as_bytes());
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key_id,
+        scope,
+        signed_headers,
+        hex(signature.as_ref())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(key.as_ref()), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        assert_eq!(uri_encode("records/a b/scan~1.pdf", true), "records/a%20b/scan~1.pdf");
+        assert_eq!(uri_encode("a/b", false), "a%2Fb");
+    }
+
+    #[test]
+    fn test_local_keys() {
+        let store = LocalStore::new("/var/lib/hims/attachments");
+        assert!(store.path("tenant/record/attachment").is_ok());
+        assert!(store.path("../etc/passwd").is_err());
+        assert!(store.path("/etc/passwd").is_err());
+        assert!(store.path("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path());
+        store.put("t/r/a", b"%PDF-1.7", "application/pdf").await.unwrap();
+        assert_eq!(store.get("t/r/a").await.unwrap(), Some(b"%PDF-1.7".to_vec()));
+        store.delete("t/r/a").await.unwrap();
+        assert_eq!(store.get("t/r/a").await.unwrap(), None);
+        store.delete("t/r/a").await.unwrap();
+    }
+}
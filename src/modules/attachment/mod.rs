@@ -0,0 +1,58 @@
+//! Attachment Module
+//!
+//! This module provides binary documents on medical records including:
+//! - PDFs, images, DICOM files and scanned consent forms
+//! - Storage on disk or in an S3-compatible bucket
+//! - Content types checked against the bytes uploaded
+//! - SHA-256 checksums verified on every download
+//! - A virus scan hook, with ClamAV supported out of the box
+//! - Mapping to FHIR DocumentReference.content
+//! - Access checked against the record, and every access audited
+
+#[path = "attachment.controller.rs"]
+pub mod attachment_controller;
+#[path = "attachment.service.rs"]
+pub mod attachment_service;
+#[path = "attachment.store.rs"]
+pub mod attachment_store;
+#[path = "attachment.scan.rs"]
+pub mod attachment_scan;
+#[path = "attachment.sql.rs"]
+pub mod attachment_sql;
+
+pub use attachment_controller::AttachmentController;
+pub use attachment_scan::{ClamdScanner, ScanVerdict, VirusScanner};
+pub use attachment_service::{Attachment, AttachmentConfig, AttachmentService, DocumentReferenceContent};
+pub use attachment_store::{AttachmentStore, LocalStore, S3Config, S3Store};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::utils::api_router::ApiRouter;
+
+/// Attachment Module Configuration
+pub struct AttachmentModule {
+    pub service: Arc<AttachmentService>,
+    pub controller: Arc<AttachmentController>,
+}
+
+impl AttachmentModule {
+    /// Create a new Attachment Module with dependency injection
+    pub fn new(db_pool: PgPool, config: AttachmentConfig, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        let service = Arc::new(AttachmentService::from_config(db_pool, config));
+        let controller = Arc::new(AttachmentController::new(service.clone(), authorization_engine));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<AttachmentService> {
+        self.service.clone()
+    }
+}
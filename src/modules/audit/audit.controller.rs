@@ -1,33 +1,25 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::Json,
-    routing::{get, post},
-    Router,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::modules::audit::audit_service::{AuditSearch, PurposeOfUseReport};
 use crate::modules::audit::AuditService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::pagination::{link_header, page_links};
+
+/// Matches across all pages of a list, when known
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
 
 /// Audit controller for compliance reporting and audit trail management
 pub struct AuditController {
     audit_service: Arc<AuditService>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct AuditQuery {
-    pub user_id: Option<String>,
-    pub patient_id: Option<String>,
-    pub resource_type: Option<String>,
-    pub event_type: Option<String>,
-    pub start_date: Option<DateTime<Utc>>,
-    pub end_date: Option<DateTime<Utc>>,
-    pub _count: Option<u32>,
-    pub _offset: Option<u32>,
-}
-
 #[derive(Debug, Deserialize)]
 pub struct HipaaReportQuery {
     pub start_date: DateTime<Utc>,
@@ -54,38 +46,51 @@ impl AuditController {
     }
 
     /// Create router with dependency injection
-    pub fn routes(&self) -> Router {
-        Router::new()
-            .route("/logs", get(Self::get_audit_logs))
-            .route("/logs/:id", get(Self::get_audit_log))
-            .route("/reports/hipaa", get(Self::generate_hipaa_report))
-            .route("/reports/user-activity", get(Self::generate_user_activity_report))
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/logs", Self::get_audit_logs, "Get audit logs with filtering")
+            .get("/logs/:id", Self::get_audit_log, "Get specific audit log by ID")
+            .get("/reports/hipaa", Self::generate_hipaa_report, "Generate HIPAA compliance report")
+            .get(
+                "/reports/purpose-of-use",
+                Self::generate_purpose_of_use_report,
+                "Generate patient data access by purpose of use report",
+            )
+            .get("/reports/user-activity", Self::generate_user_activity_report, "Generate user activity report")
             .with_state(self.audit_service.clone())
     }
 
-    /// Get audit logs with filtering
+    /// Get audit logs with filtering, a page at a time. Further pages are
+    /// linked from the `Link` header.
     pub async fn get_audit_logs(
         State(audit_service): State<Arc<AuditService>>,
-        Query(params): Query<AuditQuery>,
-    ) -> Result<Json<Vec<crate::models::AuditLog>>, (StatusCode, Json<ErrorResponse>)> {
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<(HeaderMap, Json<Vec<crate::models::AuditLog>>), (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Retrieving audit logs with params: {:?}", params);
-        
-        match audit_service
-            .get_audit_logs(
-                params.user_id.as_deref(),
-                params.patient_id.as_deref(),
-                params.resource_type.as_deref(),
-                params.event_type.as_deref(),
-                params.start_date,
-                params.end_date,
-                params._count.map(|c| c as i32),
-                params._offset.map(|o| o as i32),
+
+        let search = AuditSearch::from_params(&params).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid search parameters".to_string(),
+                    message: e.to_string(),
+                }),
             )
-            .await
-        {
-            Ok(logs) => {
-                tracing::info!("Retrieved {} audit logs", logs.len());
-                Ok(Json(logs))
+        })?;
+
+        match audit_service.get_audit_logs(&search).await {
+            Ok(result) => {
+                tracing::info!("Retrieved {} audit logs", result.logs.len());
+                let links = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+                let mut headers = HeaderMap::new();
+                if let Ok(value) = HeaderValue::from_str(&link_header(&links)) {
+                    headers.insert(header::LINK, value);
+                }
+                if let Some(total) = result.total {
+                    headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+                }
+                Ok((headers, Json(result.logs)))
             }
             Err(e) => {
                 tracing::error!("Failed to retrieve audit logs: {}", e);
@@ -164,6 +169,31 @@ impl AuditController {
         }
     }
 
+    /// Generate purpose-of-use report for minimum-necessary review
+    pub async fn generate_purpose_of_use_report(
+        State(audit_service): State<Arc<AuditService>>,
+        Query(params): Query<HipaaReportQuery>,
+    ) -> Result<Json<PurposeOfUseReport>, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Generating purpose of use report for period: {} to {}", params.start_date, params.end_date);
+
+        match audit_service
+            .generate_purpose_of_use_report(params.start_date, params.end_date)
+            .await
+        {
+            Ok(report) => Ok(Json(report)),
+            Err(e) => {
+                tracing::error!("Failed to generate purpose of use report: {}", e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to generate purpose of use report".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
     /// Generate user activity report
     pub async fn generate_user_activity_report(
         State(audit_service): State<Arc<AuditService>>,
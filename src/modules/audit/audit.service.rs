@@ -1,15 +1,129 @@
 use anyhow::Result;
-use chrono::Utc;
-use sqlx::{PgPool, Row};
-use serde_json;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres, QueryBuilder, Row, Transaction};
 use uuid::Uuid;
 
 use crate::models::{AuditLog, AuditEventType, AuditResourceType};
 use crate::core::HimsError;
+use crate::modules::authorization::PurposeOfUse;
+use crate::utils::fhir_search::TotalMode;
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
 
 // Import SQL queries from separate file
 use crate::modules::audit::audit_sql::*;
 
+/// Paging of audit log searches. The table only grows, so totals are
+/// estimated unless `_total=accurate` asks for a count.
+pub const AUDIT_PAGING: PagingPolicy = PagingPolicy::new(50, 500, TotalMode::Estimate);
+
+/// Users listed in a purpose-of-use report
+const PURPOSE_OF_USE_TOP_USERS: i64 = 20;
+
+/// Order of audit log searches, newest first
+const AUDIT_KEYS: [SortKey; 1] = [SortKey::new("timestamp", "timestamptz", true)];
+
+/// Parsed audit log search
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditSearch {
+    pub user_id: Option<Uuid>,
+    pub patient_id: Option<Uuid>,
+    pub resource_type: Option<String>,
+    pub event_type: Option<String>,
+    pub purpose_of_use: Option<PurposeOfUse>,
+    /// Logged at or after
+    pub start_date: Option<DateTime<Utc>>,
+    /// Logged before
+    pub end_date: Option<DateTime<Utc>>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for AuditSearch {
+    fn default() -> Self {
+        Self {
+            user_id: None,
+            patient_id: None,
+            resource_type: None,
+            event_type: None,
+            purpose_of_use: None,
+            start_date: None,
+            end_date: None,
+            page: PageRequest::new(AUDIT_PAGING),
+        }
+    }
+}
+
+/// A page of audit logs
+#[derive(Debug)]
+pub struct AuditSearchResult {
+    pub logs: Vec<AuditLog>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl AuditSearch {
+    /// Parse query parameters. Unknown parameters are ignored; invalid
+    /// values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+        let id = |name: &str, value: &str| Uuid::parse_str(value).map_err(|_| invalid(name, value));
+        let date = |name: &str, value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|_| invalid(name, value))
+        };
+
+        for (name, value) in params {
+            let name = name.as_str();
+            match name {
+                "user_id" => search.user_id = Some(id(name, value)?),
+                "patient_id" => search.patient_id = Some(id(name, value)?),
+                "resource_type" => search.resource_type = Some(value.clone()),
+                "event_type" => search.event_type = Some(value.clone()),
+                "purpose_of_use" => {
+                    search.purpose_of_use = Some(PurposeOfUse::parse(value).ok_or_else(|| invalid(name, value))?)
+                }
+                "start_date" => search.start_date = Some(date(name, value)?),
+                "end_date" => search.end_date = Some(date(name, value)?),
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported audit log search parameter {}", name),
+            }
+        }
+        search.page.check_cursor(&AUDIT_KEYS)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on audit logs for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(user_id) = self.user_id {
+            query.push(" AND user_id = ").push_bind(user_id);
+        }
+        if let Some(patient_id) = self.patient_id {
+            query.push(" AND patient_id = ").push_bind(patient_id);
+        }
+        if let Some(resource_type) = &self.resource_type {
+            query.push(" AND resource_type = ").push_bind(resource_type.clone());
+        }
+        if let Some(event_type) = &self.event_type {
+            query.push(" AND event_type = ").push_bind(event_type.clone());
+        }
+        if let Some(purpose) = self.purpose_of_use {
+            query.push(" AND purpose_of_use = ").push_bind(purpose.code());
+        }
+        if let Some(start_date) = self.start_date {
+            query.push(" AND timestamp >= ").push_bind(start_date);
+        }
+        if let Some(end_date) = self.end_date {
+            query.push(" AND timestamp < ").push_bind(end_date);
+        }
+    }
+}
+
 /// Service for managing audit logs and compliance reporting
 pub struct AuditService {
     pool: PgPool,
@@ -25,9 +139,24 @@ impl AuditService {
         &self,
         audit_log: &AuditLog,
     ) -> Result<String, HimsError> {
-        let audit_id = audit_log.id.clone();
-        
-        let _ = sqlx::query(INSERT_AUDIT_LOG)
+        Self::insert(&self.pool, audit_log).await?;
+        Ok(audit_log.id.clone())
+    }
+
+    /// Create an audit log entry in a transaction, so it is kept only if
+    /// the change it records commits
+    pub async fn create_audit_log_in(
+        tx: &mut Transaction<'_, Postgres>,
+        audit_log: &AuditLog,
+    ) -> Result<(), HimsError> {
+        Self::insert(&mut **tx, audit_log).await
+    }
+
+    async fn insert<'e, E>(executor: E, audit_log: &AuditLog) -> Result<(), HimsError>
+    where
+        E: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query(INSERT_AUDIT_LOG)
             .bind(&audit_log.id)
             .bind(audit_log.event_type.to_string())
             .bind(&audit_log.user_id)
@@ -41,11 +170,12 @@ impl AuditService {
             .bind(audit_log.source_ip.as_ref())
             .bind(audit_log.user_agent.as_ref())
             .bind(audit_log.details.as_ref())
-            .execute(&self.pool)
+            .bind(audit_log.session_id.as_ref())
+            .bind(audit_log.purpose_of_use.as_ref())
+            .execute(executor)
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
-
-        Ok(audit_id)
+        Ok(())
     }
 
     /// Get audit logs by user
@@ -80,6 +210,8 @@ impl AuditService {
                 timestamp: row.get("timestamp"),
                 source_ip: row.get("source_ip"),
                 user_agent: row.get("user_agent"),
+                session_id: row.get("session_id"),
+                purpose_of_use: row.get("purpose_of_use"),
                 details: row.get("details"),
             }
         }).collect();
@@ -109,6 +241,8 @@ impl AuditService {
                 timestamp: row.get("timestamp"),
                 source_ip: row.get("source_ip"),
                 user_agent: row.get("user_agent"),
+                session_id: row.get("session_id"),
+                purpose_of_use: row.get("purpose_of_use"),
                 details: row.get("details"),
             }))
         } else {
@@ -177,48 +311,64 @@ impl AuditService {
         })
     }
 
-    /// Get audit logs with optional filters
-    pub async fn get_audit_logs(
-        &self,
-        user_id: Option<&str>,
-        patient_id: Option<&str>,
-        resource_type: Option<&str>,
-        event_type: Option<&str>,
-        start_date: Option<chrono::DateTime<chrono::Utc>>,
-        end_date: Option<chrono::DateTime<chrono::Utc>>,
-        limit: Option<i32>,
-        offset: Option<i32>,
-    ) -> Result<Vec<AuditLog>, HimsError> {
-        // For simplicity, use basic query - in production you'd want proper parameter binding
-        let rows = sqlx::query("SELECT * FROM audit_logs ORDER BY timestamp DESC LIMIT 100")
+    /// Search audit logs, a page at a time
+    pub async fn get_audit_logs(&self, search: &AuditSearch) -> Result<AuditSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_AUDIT_LOGS);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &AUDIT_KEYS);
+        search.page.push_order(&mut query, &AUDIT_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+
+        let rows = query
+            .build()
             .fetch_all(&self.pool)
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
-        let logs: Vec<AuditLog> = rows.into_iter().map(|row| {
-            AuditLog {
+        let mut fetched = Vec::new();
+        for row in rows {
+            let log = AuditLog {
                 id: row.get("id"),
                 event_type: AuditEventType::from_string(&row.get::<String, _>("event_type")),
-                user_id: row.get("user_id"),
+                user_id: row.get::<Option<String>, _>("user_id").unwrap_or_default(),
                 patient_id: row.get("patient_id"),
                 appointment_id: row.get("appointment_id"),
                 resource_type: AuditResourceType::from_string(&row.get::<String, _>("resource_type")),
-                resource_id: row.get("resource_id"),
+                resource_id: row.get::<Option<String>, _>("resource_id").unwrap_or_default(),
                 action: row.get("action"),
                 outcome: row.get("outcome"),
                 timestamp: row.get("timestamp"),
                 source_ip: row.get("source_ip"),
                 user_agent: row.get("user_agent"),
+                session_id: row.get("session_id"),
+                purpose_of_use: row.get("purpose_of_use"),
                 details: row.get("details"),
-            }
-        }).collect();
+            };
+            fetched.push((log, RowKey::from_column(row.get("page_key"))?));
+        }
+        let page = search.page.page(fetched);
 
-        Ok(logs)
+        let total = count_matches(
+            &self.pool,
+            search.page.total(),
+            COUNT_AUDIT_LOGS,
+            ESTIMATE_AUDIT_LOGS,
+            |query| search.push_filters(query),
+        )
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(AuditSearchResult {
+            logs: page.items,
+            total,
+            next: page.next,
+            previous: page.previous,
+        })
     }
 
     /// Get single audit log entry - compatibility method
     pub async fn get_audit_log(&self, id: &str) -> Result<Option<AuditLog>, HimsError> {
-        let row = sqlx::query("SELECT * FROM audit_logs WHERE id = $1")
+        let row = sqlx::query(GET_AUDIT_LOG_BY_ID)
             .bind(id)
             .fetch_optional(&self.pool)
             .await
@@ -239,6 +389,8 @@ impl AuditService {
                     timestamp: row.get("timestamp"),
                     source_ip: row.get("source_ip"),
                     user_agent: row.get("user_agent"),
+                    session_id: row.get("session_id"),
+                    purpose_of_use: row.get("purpose_of_use"),
                     details: row.get("details"),
                 };
                 Ok(Some(log))
@@ -265,6 +417,90 @@ impl AuditService {
         };
         Ok(report)
     }
+
+    /// Report who accessed patient data for which purpose over a period
+    pub async fn generate_purpose_of_use_report(
+        &self,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Result<PurposeOfUseReport, HimsError> {
+        let breakdown: Vec<PurposeOfUseSummary> = sqlx::query(GET_PURPOSE_OF_USE_SUMMARY)
+            .bind(start_date)
+            .bind(end_date)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(|row| PurposeOfUseSummary {
+                purpose_of_use: row.get("purpose_of_use"),
+                resource_type: row.get("resource_type"),
+                access_events: row.get::<i64, _>("access_events") as u64,
+                unique_users: row.get::<i64, _>("unique_users") as u64,
+                unique_patients: row.get::<i64, _>("unique_patients") as u64,
+            })
+            .collect();
+
+        let top_users = sqlx::query(GET_PURPOSE_OF_USE_TOP_USERS)
+            .bind(start_date)
+            .bind(end_date)
+            .bind(PURPOSE_OF_USE_TOP_USERS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(|row| PurposeOfUseUser {
+                user_id: row.get("user_id"),
+                purpose_of_use: row.get("purpose_of_use"),
+                access_events: row.get::<i64, _>("access_events") as u64,
+                unique_patients: row.get::<i64, _>("unique_patients") as u64,
+            })
+            .collect();
+
+        Ok(PurposeOfUseReport {
+            report_period: format!("{} to {}", start_date.format("%Y-%m-%d"), end_date.format("%Y-%m-%d")),
+            total_access_events: breakdown.iter().map(|summary| summary.access_events).sum(),
+            undeclared_access_events: breakdown
+                .iter()
+                .filter(|summary| summary.purpose_of_use.is_none())
+                .map(|summary| summary.access_events)
+                .sum(),
+            breakdown,
+            top_users,
+        })
+    }
+}
+
+/// Patient data access by purpose of use, for HIPAA minimum-necessary
+/// review
+#[derive(Debug, serde::Serialize)]
+pub struct PurposeOfUseReport {
+    pub report_period: String,
+    pub total_access_events: u64,
+    /// Accesses that declared no purpose of use
+    pub undeclared_access_events: u64,
+    pub breakdown: Vec<PurposeOfUseSummary>,
+    /// Users reaching the most patients under a purpose, busiest first
+    pub top_users: Vec<PurposeOfUseUser>,
+}
+
+/// Access under one purpose of use to one resource type
+#[derive(Debug, serde::Serialize)]
+pub struct PurposeOfUseSummary {
+    /// HL7 PurposeOfUse code; `None` when undeclared
+    pub purpose_of_use: Option<String>,
+    pub resource_type: String,
+    pub access_events: u64,
+    pub unique_users: u64,
+    pub unique_patients: u64,
+}
+
+/// One user's access under one purpose of use
+#[derive(Debug, serde::Serialize)]
+pub struct PurposeOfUseUser {
+    pub user_id: String,
+    pub purpose_of_use: Option<String>,
+    pub access_events: u64,
+    pub unique_patients: u64,
 }
 
 /// HIPAA compliance report structure
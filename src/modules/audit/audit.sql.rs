@@ -1,26 +1,29 @@
-/// Audit SQL Queries
-/// 
-/// This file contains all SQL queries used by the audit service
-/// for clean separation of concerns and better maintainability.
+//! Audit SQL Queries
+//! 
+//! This file contains all SQL queries used by the audit service
+//! for clean separation of concerns and better maintainability.
 
-/// Insert a new audit log entry
+/// Insert a new audit log entry; IDs are bound as text
 pub const INSERT_AUDIT_LOG: &str = r#"
     INSERT INTO audit_logs (
         id, event_type, user_id, patient_id, appointment_id, resource_type,
-        resource_id, action, outcome, timestamp, source_ip, user_agent, details
+        resource_id, action, outcome, timestamp, source_ip, user_agent, details,
+        session_id, purpose_of_use
     ) VALUES (
-        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13
+        $1::uuid, $2, NULLIF($3, '')::uuid, $4::uuid, $5::uuid, $6, NULLIF($7, '')::uuid, $8, $9, $10,
+        $11::inet, $12, $13, $14, $15
     )
 "#;
 
 /// Get audit logs by user ID
 pub const GET_AUDIT_LOGS_BY_USER: &str = r#"
     SELECT 
-        id, event_type, user_id, patient_id, appointment_id,
-        resource_type, resource_id, action, outcome, timestamp,
-        source_ip, user_agent, details
+        id::text AS id, event_type, user_id::text AS user_id, patient_id::text AS patient_id,
+        appointment_id::text AS appointment_id, resource_type, resource_id::text AS resource_id,
+        action, outcome, timestamp, host(source_ip) AS source_ip, user_agent, details,
+        session_id, purpose_of_use
     FROM audit_logs
-    WHERE user_id = $1
+    WHERE user_id = $1::uuid
     ORDER BY timestamp DESC
     LIMIT $2 OFFSET $3
 "#;
@@ -28,11 +31,12 @@ pub const GET_AUDIT_LOGS_BY_USER: &str = r#"
 /// Get audit log by ID
 pub const GET_AUDIT_LOG_BY_ID: &str = r#"
     SELECT 
-        id, event_type, user_id, patient_id, appointment_id,
-        resource_type, resource_id, action, outcome, timestamp,
-        source_ip, user_agent, details
+        id::text AS id, event_type, user_id::text AS user_id, patient_id::text AS patient_id,
+        appointment_id::text AS appointment_id, resource_type, resource_id::text AS resource_id,
+        action, outcome, timestamp, host(source_ip) AS source_ip, user_agent, details,
+        session_id, purpose_of_use
     FROM audit_logs
-    WHERE id = $1
+    WHERE id = $1::uuid
 "#;
 
 /// Get access statistics for HIPAA compliance
@@ -76,11 +80,66 @@ pub const GET_USER_ACTIVITY: &str = r#"
 /// Get audit logs by patient ID
 pub const GET_AUDIT_LOGS_BY_PATIENT: &str = r#"
     SELECT 
-        id, event_type, user_id, patient_id, appointment_id,
-        resource_type, resource_id, action, outcome, timestamp,
-        source_ip, user_agent, details
+        id::text AS id, event_type, user_id::text AS user_id, patient_id::text AS patient_id,
+        appointment_id::text AS appointment_id, resource_type, resource_id::text AS resource_id,
+        action, outcome, timestamp, host(source_ip) AS source_ip, user_agent, details,
+        session_id, purpose_of_use
     FROM audit_logs
-    WHERE patient_id = $1
+    WHERE patient_id = $1::uuid
     ORDER BY timestamp DESC
     LIMIT $2 OFFSET $3
-"#;
\ No newline at end of file
+"#;
+/// Start of an audit log search; callers append `AND` filters, the page
+/// cursor, order and limit
+pub const SEARCH_AUDIT_LOGS: &str = r#"
+    SELECT 
+        id::text AS id, event_type, user_id::text AS user_id,
+        patient_id::text AS patient_id, NULL::text AS appointment_id,
+        resource_type, resource_id::text AS resource_id, action, outcome,
+        timestamp, host(source_ip) AS source_ip, user_agent, details,
+        session_id, purpose_of_use,
+        ARRAY[timestamp::text, id::text] AS page_key
+    FROM audit_logs
+    WHERE TRUE
+"#;
+
+/// Count audit logs matching appended filters
+pub const COUNT_AUDIT_LOGS: &str = r#"
+    SELECT COUNT(*) FROM audit_logs WHERE TRUE
+"#;
+
+/// Planner's row estimate for audit logs matching appended filters
+pub const ESTIMATE_AUDIT_LOGS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM audit_logs WHERE TRUE
+"#;
+
+/// Patient data access by purpose of use and resource type, for HIPAA
+/// minimum-necessary review
+pub const GET_PURPOSE_OF_USE_SUMMARY: &str = r#"
+    SELECT
+        purpose_of_use,
+        resource_type,
+        COUNT(*) AS access_events,
+        COUNT(DISTINCT user_id) AS unique_users,
+        COUNT(DISTINCT patient_id) AS unique_patients
+    FROM audit_logs
+    WHERE patient_id IS NOT NULL
+        AND timestamp >= $1 AND timestamp < $2
+    GROUP BY purpose_of_use, resource_type
+    ORDER BY purpose_of_use NULLS FIRST, access_events DESC
+"#;
+
+/// Users reaching the most patients under each purpose of use
+pub const GET_PURPOSE_OF_USE_TOP_USERS: &str = r#"
+    SELECT
+        user_id::text AS user_id,
+        purpose_of_use,
+        COUNT(*) AS access_events,
+        COUNT(DISTINCT patient_id) AS unique_patients
+    FROM audit_logs
+    WHERE patient_id IS NOT NULL
+        AND timestamp >= $1 AND timestamp < $2
+    GROUP BY user_id, purpose_of_use
+    ORDER BY unique_patients DESC, access_events DESC
+    LIMIT $3
+"#;
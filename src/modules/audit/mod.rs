@@ -5,7 +5,7 @@
 //! - GDPR compliance logging
 //! - Security event tracking
 //! - Access control monitoring
-//! - Compliance reporting
+//! - Compliance reporting, including access by purpose of use
 
 #[path = "audit.controller.rs"]
 pub mod audit_controller;
@@ -15,12 +15,13 @@ pub mod audit_service;
 pub mod audit_sql;
 
 pub use audit_controller::AuditController;
-pub use audit_service::AuditService;
+pub use audit_service::{AuditSearch, AuditSearchResult, AuditService};
 
-use axum::Router;
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::utils::api_router::ApiRouter;
+
 /// Audit Module Configuration
 pub struct AuditModule {
     pub service: Arc<AuditService>,
@@ -40,7 +41,7 @@ impl AuditModule {
     }
 
     /// Register routes for this module
-    pub fn routes(&self) -> Router {
+    pub fn routes(&self) -> ApiRouter {
         self.controller.routes()
     }
 
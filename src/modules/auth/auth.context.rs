@@ -0,0 +1,280 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, Extensions, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::AuditLog;
+use crate::modules::auth::auth_jwt::{token_verifier, AccessClaims};
+use crate::modules::auth::{AuthMiddleware, AuthenticatedUser};
+use crate::modules::authorization::PurposeOfUse;
+use crate::modules::rate_limit::RateLimitService;
+use crate::utils::auth::{extract_dev_user, extract_purpose_of_use, extract_session_id};
+
+/// Header naming why records are being accessed, as an HL7 PurposeOfUse
+/// code such as `TREAT` or a purpose name such as `treatment`
+pub const PURPOSE_OF_USE_HEADER: &str = "x-purpose-of-use";
+
+/// Longest session ID recorded
+const MAX_SESSION_ID_CHARS: usize = 255;
+
+/// Longest user agent recorded
+const MAX_USER_AGENT_CHARS: usize = 512;
+
+/// The authenticated principal of a request: who is acting, in which
+/// session, why and from where. Controllers take it as an extractor and
+/// pass it to services, so audit logs record the real user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthContext {
+    pub user_id: Uuid,
+    /// Session the user signed in with: the token's refresh family, else
+    /// the session cookie or `X-Session-ID`
+    pub session_id: Option<String>,
+    /// Declared purpose of use; unrecognized purposes are none
+    pub purpose_of_use: Option<PurposeOfUse>,
+    /// Address of the connection, or the client a trusted proxy forwarded
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AuthContext {
+    /// Context of a user acting outside any request
+    pub fn new(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            session_id: None,
+            purpose_of_use: None,
+            source_ip: None,
+            user_agent: None,
+        }
+    }
+
+    /// Context of a request carrying a verified bearer token, or naming its
+    /// user with `X-User-ID` where `HIMS_DEV_USER_HEADER` allows it; `None`
+    /// when the request is unauthenticated. Headers alone cannot say where
+    /// a request came from, so it has no source IP.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Self::resolve(None, headers, None)
+    }
+
+    /// Context of the principal authentication attached to a request, a
+    /// user or an API key's service account, else of its verified headers
+    fn resolve(user: Option<&AuthenticatedUser>, headers: &HeaderMap, source_ip: Option<String>) -> Option<Self> {
+        let claims = Self::token_claims(headers);
+        let user_id = match user {
+            Some(user) => Uuid::parse_str(&user.id).ok()?,
+            None => claims
+                .as_ref()
+                .and_then(|claims| Uuid::parse_str(&claims.sub).ok())
+                .or_else(|| extract_dev_user(headers))?,
+        };
+        let session_id = claims
+            .and_then(|claims| claims.sid)
+            .or_else(|| extract_session_id(headers))
+            .map(|id| id.chars().take(MAX_SESSION_ID_CHARS).collect());
+
+        Some(Self {
+            user_id,
+            session_id,
+            purpose_of_use: extract_purpose_of_use(headers),
+            source_ip,
+            user_agent: Self::header(headers, "user-agent", MAX_USER_AGENT_CHARS),
+        })
+    }
+
+    /// Middleware resolving the request's principal once, into its
+    /// extensions, for the handlers and layers inside it. Requests without
+    /// one are rejected with 401 unless the route is public. The source IP
+    /// is the client the rate limiter counts: `X-Forwarded-For` is only
+    /// believed from its trusted proxies.
+    pub async fn attach(
+        State(rate_limit): State<Arc<RateLimitService>>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let source_ip = rate_limit.client(Self::peer(request.extensions()), request.headers()).ip_address;
+        let context = Self::resolve(request.extensions().get::<AuthenticatedUser>(), request.headers(), source_ip);
+        match context {
+            Some(context) => {
+                request.extensions_mut().insert(context);
+                next.run(request).await
+            }
+            None if AuthMiddleware::is_public(request.uri().path()) => next.run(request).await,
+            None => Self::unauthorized().into_response(),
+        }
+    }
+
+    /// Stamp an audit log with this principal
+    pub fn audit(&self, log: AuditLog) -> AuditLog {
+        log.with_user(self.user_id)
+            .with_session(self.session_id.clone(), self.purpose_of_use.map(|purpose| purpose.code().to_string()))
+            .with_source_info(self.source_ip.clone(), self.user_agent.clone())
+    }
+
+    /// Address of the connection a request arrived on, when the server is
+    /// run with `into_make_service_with_connect_info`
+    fn peer(extensions: &Extensions) -> Option<IpAddr> {
+        extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip())
+    }
+
+    /// Claims of a verified bearer token
+    fn token_claims(headers: &HeaderMap) -> Option<AccessClaims> {
+        let token = headers
+            .get("authorization")?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        token_verifier()?.validate_access_token(token).ok()
+    }
+
+    fn unauthorized() -> (StatusCode, Json<Value>) {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "message": "Invalid or missing authentication",
+            })),
+        )
+    }
+
+    /// A header's trimmed text, truncated to `max_chars`
+    fn header(headers: &HeaderMap, name: &str, max_chars: usize) -> Option<String> {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(max_chars).collect())
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthContext {
+    type Rejection = (StatusCode, Json<Value>);
+
+    /// The context `attach` resolved, else one resolved from the headers,
+    /// so handlers mounted outside the middleware still authenticate. Those
+    /// have no trusted proxies, so their source IP is the connection's.
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(context) = parts.extensions.get::<AuthContext>() {
+            return Ok(context.clone());
+        }
+        let source_ip = Self::peer(&parts.extensions).map(|ip| ip.to_string());
+        Self::resolve(parts.extensions.get::<AuthenticatedUser>(), &parts.headers, source_ip)
+            .ok_or_else(Self::unauthorized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AuditAction, AuditEventType};
+    use crate::modules::auth::auth_jwt::test_tokens;
+
+    #[test]
+    fn test_from_headers() {
+        let user_id = Uuid::new_v4();
+        let tokens = test_tokens(&AuthenticatedUser {
+            id: user_id.to_string(),
+            username: "clinician".to_string(),
+            role: "physician".to_string(),
+            permissions: vec![],
+        });
+        let mut headers = HeaderMap::new();
+        assert!(AuthContext::from_headers(&headers).is_none());
+
+        // An unverified user header names no one
+        headers.insert("x-user-id", user_id.to_string().parse().unwrap());
+        assert!(AuthContext::from_headers(&headers).is_none());
+
+        let bearer = format!("Bearer {}", tokens.access_token);
+        headers.insert("authorization", bearer.parse().unwrap());
+        headers.insert("x-session-id", "session-1".parse().unwrap());
+        headers.insert(PURPOSE_OF_USE_HEADER, " TREAT ".parse().unwrap());
+        headers.insert("x-forwarded-for", "10.0.0.7, 10.0.0.1".parse().unwrap());
+        let context = AuthContext::from_headers(&headers).unwrap();
+        assert_eq!(context.user_id, user_id);
+        assert_eq!(context.session_id, Some(tokens.session_id.clone()));
+        assert_eq!(context.purpose_of_use, Some(PurposeOfUse::Treatment));
+        // Forwarding headers are not believed without the connection they came on
+        assert_eq!(context.source_ip, None);
+
+        let log = context.audit(AuditLog::new(AuditEventType::Read, AuditAction::Read, "Patient".to_string()));
+        assert_eq!(log.user_id, user_id.to_string());
+        assert_eq!(log.session_id, Some(tokens.session_id));
+        assert_eq!(log.purpose_of_use.as_deref(), Some("TREAT"));
+
+        headers.insert(PURPOSE_OF_USE_HEADER, "Research".parse().unwrap());
+        let context = AuthContext::from_headers(&headers).unwrap();
+        assert_eq!(context.purpose_of_use, Some(PurposeOfUse::Research));
+        headers.insert(PURPOSE_OF_USE_HEADER, "curiosity".parse().unwrap());
+        assert_eq!(AuthContext::from_headers(&headers).unwrap().purpose_of_use, None);
+    }
+
+    #[test]
+    fn test_attached_principal() {
+        let account_id = Uuid::new_v4();
+        let service = AuthenticatedUser {
+            id: account_id.to_string(),
+            username: "lab-interface".to_string(),
+            role: "service_account".to_string(),
+            permissions: vec![],
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("x-user-id", Uuid::new_v4().to_string().parse().unwrap());
+        headers.insert("authorization", "Bearer forged".parse().unwrap());
+        assert!(AuthContext::resolve(None, &headers, None).is_none());
+
+        // The principal authentication attached wins over anything in the headers
+        let context = AuthContext::resolve(Some(&service), &headers, None).unwrap();
+        assert_eq!(context.user_id, account_id);
+        assert_eq!(context.session_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_source_ip_from_trusted_proxies() {
+        use crate::modules::rate_limit::{MemoryStore, RateLimitConfig, TrustedProxy};
+        use axum::{body::Body, routing::get, Router};
+
+        let user_id = Uuid::new_v4();
+        let tokens = test_tokens(&AuthenticatedUser {
+            id: user_id.to_string(),
+            username: "clinician".to_string(),
+            role: "physician".to_string(),
+            permissions: vec![],
+        });
+        let config = RateLimitConfig {
+            trusted_proxies: vec![TrustedProxy::parse("10.0.0.0/8").unwrap()],
+            ..RateLimitConfig::default()
+        };
+        let rate_limit = Arc::new(RateLimitService::new(config, Arc::new(MemoryStore::new())));
+        let mut router = Router::new()
+            .route("/whoami", get(|auth: AuthContext| async move { auth.source_ip.unwrap_or_default() }))
+            .layer(axum::middleware::from_fn_with_state(rate_limit, AuthContext::attach));
+
+        let mut source_ip = |peer: &str| {
+            let mut request = Request::builder()
+                .uri("/whoami")
+                .header("authorization", format!("Bearer {}", tokens.access_token))
+                .header("x-forwarded-for", "203.0.113.9, 10.0.0.2")
+                .body(Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 443)));
+            let response = tower::Service::call(&mut router, request);
+            async move {
+                let body = axum::body::to_bytes(response.await.unwrap().into_body(), usize::MAX).await.unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+
+        // A client's own forwarding header is ignored, a trusted proxy's believed
+        assert_eq!(source_ip("192.0.2.50").await, "192.0.2.50");
+        assert_eq!(source_ip("10.0.0.1").await, "203.0.113.9");
+    }
+}
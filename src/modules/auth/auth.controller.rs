@@ -2,13 +2,15 @@ use axum::{
     extract::State,
     http::StatusCode,
     response::Json,
-    routing::post,
-    Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::core::HimsError;
+use crate::modules::auth::auth_jwt::TokenPair;
+use crate::modules::auth::session_service::SessionClient;
 use crate::modules::auth::{AuthService, AuthenticatedUser};
+use crate::utils::api_router::ApiRouter;
 
 /// Authentication controller
 pub struct AuthController {
@@ -25,7 +27,21 @@ pub struct LoginRequest {
 pub struct LoginResponse {
     pub user: AuthenticatedUser,
     pub token: String,
+    pub refresh_token: String,
+    pub token_type: String,
     pub expires_in: u64,
+    pub session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeRequest {
+    pub token: Option<String>,
+    pub refresh_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -52,32 +68,38 @@ impl AuthController {
     }
 
     /// Create router with dependency injection
-    pub fn routes(&self) -> Router {
-        Router::new()
-            .route("/login", post(Self::login))
-            .route("/validate", post(Self::validate_token))
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/login", Self::login, "User login endpoint")
+            .post("/validate", Self::validate_token, "Token validation endpoint")
+            .post("/refresh", Self::refresh, "Exchange a refresh token for a new token pair")
+            .post("/revoke", Self::revoke, "Revoke an access token and/or a refresh token family (logout)")
             .with_state(self.auth_service.clone())
     }
 
     /// User login endpoint
     pub async fn login(
         State(auth_service): State<Arc<AuthService>>,
+        client: SessionClient,
         Json(payload): Json<LoginRequest>,
     ) -> Result<Json<LoginResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Login attempt for user: {}", payload.username);
-        
+
         match auth_service
             .authenticate(&payload.username, &payload.password)
             .await
         {
             Ok(Some(user)) => {
-                match auth_service.generate_token(&user).await {
-                    Ok(token) => {
+                match auth_service.issue_tokens(&user, &client).await {
+                    Ok(tokens) => {
                         tracing::info!("User {} logged in successfully", user.username);
                         Ok(Json(LoginResponse {
                             user,
-                            token,
-                            expires_in: 3600, // 1 hour
+                            token: tokens.access_token,
+                            refresh_token: tokens.refresh_token,
+                            token_type: tokens.token_type,
+                            expires_in: tokens.expires_in as u64,
+                            session_id: tokens.session_id,
                         }))
                     }
                     Err(e) => {
@@ -94,6 +116,9 @@ impl AuthController {
             }
             Ok(None) => {
                 tracing::warn!("Invalid login attempt for user: {}", payload.username);
+                if let Err(e) = auth_service.record_failed_login(&payload.username, &client).await {
+                    tracing::error!("Failed to record login attempt: {}", e);
+                }
                 Err((
                     StatusCode::UNAUTHORIZED,
                     Json(ErrorResponse {
@@ -102,6 +127,26 @@ impl AuthController {
                     }),
                 ))
             }
+            Err(HimsError::SecurityError { message }) => {
+                tracing::warn!("Login rejected for locked user {}: {}", payload.username, message);
+                Err((
+                    StatusCode::LOCKED,
+                    Json(ErrorResponse {
+                        error: "Account locked".to_string(),
+                        message,
+                    }),
+                ))
+            }
+            Err(HimsError::AuthenticationError { message }) => {
+                tracing::info!("User {} must change password: {}", payload.username, message);
+                Err((
+                    StatusCode::FORBIDDEN,
+                    Json(ErrorResponse {
+                        error: "Password change required".to_string(),
+                        message,
+                    }),
+                ))
+            }
             Err(e) => {
                 tracing::error!("Authentication error for user {}: {}", payload.username, e);
                 Err((
@@ -149,4 +194,72 @@ impl AuthController {
             }
         }
     }
+
+    /// Exchange a refresh token for a new token pair
+    pub async fn refresh(
+        State(auth_service): State<Arc<AuthService>>,
+        Json(payload): Json<RefreshRequest>,
+    ) -> Result<Json<TokenPair>, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Token refresh request");
+
+        match auth_service.refresh_tokens(&payload.refresh_token).await {
+            Ok(Some(tokens)) => Ok(Json(tokens)),
+            Ok(None) => {
+                tracing::warn!("Invalid or reused refresh token");
+                Err((
+                    StatusCode::UNAUTHORIZED,
+                    Json(ErrorResponse {
+                        error: "Invalid refresh token".to_string(),
+                        message: "Refresh token is invalid, expired or revoked".to_string(),
+                    }),
+                ))
+            }
+            Err(e) => {
+                tracing::error!("Token refresh error: {}", e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Token refresh failed".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// Revoke an access token and/or a refresh token family (logout)
+    pub async fn revoke(
+        State(auth_service): State<Arc<AuthService>>,
+        Json(payload): Json<RevokeRequest>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Token revocation request");
+
+        if let Some(token) = payload.token.as_deref() {
+            if let Err(e) = auth_service.revoke_access_token(token, "logout").await {
+                tracing::warn!("Failed to revoke access token: {}", e);
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "Token revocation failed".to_string(),
+                        message: e.to_string(),
+                    }),
+                ));
+            }
+        }
+
+        if let Some(refresh_token) = payload.refresh_token.as_deref() {
+            if let Err(e) = auth_service.revoke_refresh_token(refresh_token, "logout").await {
+                tracing::error!("Failed to revoke refresh token: {}", e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Token revocation failed".to_string(),
+                        message: e.to_string(),
+                    }),
+                ));
+            }
+        }
+
+        Ok(StatusCode::NO_CONTENT)
+    }
 }
\ No newline at end of file
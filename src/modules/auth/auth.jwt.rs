@@ -0,0 +1,586 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::current_tenant_id;
+use crate::modules::auth::AuthenticatedUser;
+
+/// Process-wide verifier used by header-based user extraction
+static TOKEN_VERIFIER: OnceLock<Arc<JwtManager>> = OnceLock::new();
+
+/// Install the verifier used by `utils::auth`. Only the first call takes effect.
+pub fn install_token_verifier(manager: Arc<JwtManager>) {
+    let _ = TOKEN_VERIFIER.set(manager);
+}
+
+/// Verifier installed at startup, if any
+pub fn token_verifier() -> Option<Arc<JwtManager>> {
+    TOKEN_VERIFIER.get().cloned()
+}
+
+/// Tokens for a user, signed by the process-wide verifier, which is
+/// installed with an ephemeral key if no test installed one yet
+#[cfg(test)]
+pub(crate) fn test_tokens(user: &AuthenticatedUser) -> TokenPair {
+    let config = JwtConfig {
+        private_key_pem: None,
+        public_key_pem: None,
+        external_issuers: vec![],
+        ..JwtConfig::default()
+    };
+    TOKEN_VERIFIER.get_or_init(|| Arc::new(JwtManager::from_config(config).unwrap()));
+    token_verifier().unwrap().issue_tokens(user, Uuid::new_v4()).unwrap()
+}
+
+/// Signing algorithms supported for HIMS-issued tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JwtAlgorithm {
+    RS256,
+    ES256,
+}
+
+impl JwtAlgorithm {
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            JwtAlgorithm::RS256 => Algorithm::RS256,
+            JwtAlgorithm::ES256 => Algorithm::ES256,
+        }
+    }
+}
+
+/// External identity provider whose tokens are accepted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalIssuer {
+    pub issuer: String,
+    pub jwks_uri: String,
+    pub audience: String,
+    /// HIMS tenant of each of the provider's own tenants, keyed by its
+    /// `tid` claim, e.g. an Azure AD directory ID
+    #[serde(default)]
+    pub tenants: HashMap<String, Uuid>,
+    /// HIMS tenant of tokens whose `tid` is not mapped in `tenants`
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+}
+
+/// JWT issuance and validation configuration
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    pub issuer: String,
+    pub audience: String,
+    pub algorithm: JwtAlgorithm,
+    /// PEM-encoded signing key. When unset an ephemeral ES256 key is generated.
+    pub private_key_pem: Option<String>,
+    pub public_key_pem: Option<String>,
+    pub key_id: String,
+    pub access_token_ttl_secs: i64,
+    pub refresh_token_ttl_secs: i64,
+    pub leeway_secs: u64,
+    pub external_issuers: Vec<ExternalIssuer>,
+    /// Minimum time between JWKS refreshes triggered by unknown key IDs
+    pub jwks_min_refresh_secs: u64,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        let read_pem = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .and_then(|path| std::fs::read_to_string(path).ok())
+        };
+
+        Self {
+            issuer: std::env::var("JWT_ISSUER").unwrap_or_else(|_| "open-hims".to_string()),
+            audience: std::env::var("JWT_AUDIENCE").unwrap_or_else(|_| "hims-api".to_string()),
+            algorithm: match std::env::var("JWT_ALGORITHM").as_deref() {
+                Ok("RS256") => JwtAlgorithm::RS256,
+                _ => JwtAlgorithm::ES256,
+            },
+            private_key_pem: read_pem("JWT_PRIVATE_KEY_PATH"),
+            public_key_pem: read_pem("JWT_PUBLIC_KEY_PATH"),
+            key_id: std::env::var("JWT_KEY_ID").unwrap_or_else(|_| "hims-1".to_string()),
+            access_token_ttl_secs: 900, // 15 minutes
+            refresh_token_ttl_secs: 30 * 24 * 3600, // 30 days
+            leeway_secs: 60,
+            external_issuers: std::env::var("JWT_EXTERNAL_ISSUERS")
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            jwks_min_refresh_secs: 60,
+        }
+    }
+}
+
+/// Claims carried by access tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessClaims {
+    pub sub: String,
+    pub iss: String,
+    /// String or array of strings per RFC 7519
+    pub aud: serde_json::Value,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(default)]
+    pub nbf: Option<i64>,
+    #[serde(default)]
+    pub jti: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    /// Refresh token family the access token was issued from
+    #[serde(default)]
+    pub sid: Option<String>,
+    /// Tenant the token was issued in
+    #[serde(default)]
+    pub tid: Option<String>,
+}
+
+impl AccessClaims {
+    /// Convert validated claims into the authenticated user
+    pub fn to_user(&self) -> AuthenticatedUser {
+        AuthenticatedUser {
+            id: self.sub.clone(),
+            username: self.username.clone().unwrap_or_else(|| self.sub.clone()),
+            role: self.role.clone().unwrap_or_default(),
+            permissions: self.permissions.clone(),
+        }
+    }
+}
+
+/// Access and refresh token returned on login and refresh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    /// Login session the tokens belong to (the refresh token family)
+    pub session_id: String,
+    #[serde(skip)]
+    pub access_jti: String,
+    #[serde(skip)]
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+/// Verification key discovered from an external issuer's JWKS
+struct ExternalKey {
+    issuer: String,
+    audience: String,
+    key: DecodingKey,
+    params: AlgorithmParameters,
+}
+
+/// Cached JWKS keys for external identity providers, indexed by key ID
+struct JwksCache {
+    keys: RwLock<HashMap<String, ExternalKey>>,
+    last_refresh: RwLock<Option<Instant>>,
+}
+
+/// Issues and validates HIMS JWTs
+pub struct JwtManager {
+    config: JwtConfig,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwks: JwksCache,
+    /// Revoked access token IDs with their expiry (unix seconds)
+    revoked: RwLock<HashMap<String, i64>>,
+    rng: SystemRandom,
+}
+
+impl JwtManager {
+    /// Build a manager from configuration, generating an ephemeral ES256 key
+    /// when no signing key is configured
+    pub fn from_config(config: JwtConfig) -> Result<Self, HimsError> {
+        let rng = SystemRandom::new();
+        let configuration_error = |message: String| HimsError::ConfigurationError { message };
+
+        let (algorithm, encoding_key, decoding_key) = match (&config.private_key_pem, &config.public_key_pem) {
+            (Some(private_pem), Some(public_pem)) => match config.algorithm {
+                JwtAlgorithm::RS256 => (
+                    config.algorithm.algorithm(),
+                    EncodingKey::from_rsa_pem(private_pem.as_bytes())
+                        .map_err(|e| configuration_error(format!("Invalid RSA private key: {}", e)))?,
+                    DecodingKey::from_rsa_pem(public_pem.as_bytes())
+                        .map_err(|e| configuration_error(format!("Invalid RSA public key: {}", e)))?,
+                ),
+                JwtAlgorithm::ES256 => (
+                    config.algorithm.algorithm(),
+                    EncodingKey::from_ec_pem(private_pem.as_bytes())
+                        .map_err(|e| configuration_error(format!("Invalid EC private key: {}", e)))?,
+                    DecodingKey::from_ec_pem(public_pem.as_bytes())
+                        .map_err(|e| configuration_error(format!("Invalid EC public key: {}", e)))?,
+                ),
+            },
+            (None, None) => {
+                tracing::warn!("No JWT signing key configured; generating an ephemeral ES256 key");
+                let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+                    .map_err(|_| configuration_error("Failed to generate signing key".to_string()))?;
+                let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                    .map_err(|_| configuration_error("Failed to load generated signing key".to_string()))?;
+                (
+                    Algorithm::ES256,
+                    EncodingKey::from_ec_der(pkcs8.as_ref()),
+                    DecodingKey::from_ec_der(key_pair.public_key().as_ref()),
+                )
+            }
+            _ => {
+                return Err(configuration_error(
+                    "Both JWT private and public keys must be configured".to_string(),
+                ));
+            }
+        };
+
+        Ok(Self {
+            config,
+            algorithm,
+            encoding_key,
+            decoding_key,
+            jwks: JwksCache {
+                keys: RwLock::new(HashMap::new()),
+                last_refresh: RwLock::new(None),
+            },
+            revoked: RwLock::new(HashMap::new()),
+            rng,
+        })
+    }
+
+    pub fn config(&self) -> &JwtConfig {
+        &self.config
+    }
+
+    /// Issue an access token and a new opaque refresh token for a user.
+    /// `family_id` ties rotated refresh tokens together for reuse detection.
+    pub fn issue_tokens(&self, user: &AuthenticatedUser, family_id: Uuid) -> Result<TokenPair, HimsError> {
+        let now = Utc::now();
+        let jti = Uuid::new_v4().to_string();
+        let claims = AccessClaims {
+            sub: user.id.clone(),
+            iss: self.config.issuer.clone(),
+            aud: serde_json::Value::String(self.config.audience.clone()),
+            exp: now.timestamp() + self.config.access_token_ttl_secs,
+            iat: now.timestamp(),
+            nbf: Some(now.timestamp()),
+            jti: jti.clone(),
+            username: Some(user.username.clone()),
+            role: Some(user.role.clone()),
+            permissions: user.permissions.clone(),
+            sid: Some(family_id.to_string()),
+            tid: Some(current_tenant_id().to_string()),
+        };
+
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.config.key_id.clone());
+
+        let access_token = encode(&header, &claims, &self.encoding_key)
+            .map_err(|e| HimsError::InternalError { message: format!("Failed to sign token: {}", e) })?;
+
+        Ok(TokenPair {
+            access_token,
+            refresh_token: self.generate_refresh_token()?,
+            token_type: "Bearer".to_string(),
+            expires_in: self.config.access_token_ttl_secs,
+            session_id: family_id.to_string(),
+            access_jti: jti,
+            refresh_expires_at: now + chrono::Duration::seconds(self.config.refresh_token_ttl_secs),
+        })
+    }
+
+    /// Issue a short-lived access token for a service account. Service tokens
+    /// carry no session and cannot be refreshed; clients re-run the
+    /// client-credentials grant instead.
+    pub fn issue_service_token(&self, principal: &AuthenticatedUser, ttl_secs: i64) -> Result<String, HimsError> {
+        let now = Utc::now();
+        let claims = AccessClaims {
+            sub: principal.id.clone(),
+            iss: self.config.issuer.clone(),
+            aud: serde_json::Value::String(self.config.audience.clone()),
+            exp: now.timestamp() + ttl_secs,
+            iat: now.timestamp(),
+            nbf: Some(now.timestamp()),
+            jti: Uuid::new_v4().to_string(),
+            username: Some(principal.username.clone()),
+            role: Some(principal.role.clone()),
+            permissions: principal.permissions.clone(),
+            sid: None,
+            tid: Some(current_tenant_id().to_string()),
+        };
+
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.config.key_id.clone());
+
+        encode(&header, &claims, &self.encoding_key)
+            .map_err(|e| HimsError::InternalError { message: format!("Failed to sign token: {}", e) })
+    }
+
+    /// HIMS tenant a validated token is bound to. Our own tokens carry it in
+    /// `tid`; an external issuer's `tid` names its own tenant, so it only
+    /// binds through the issuer's configured mapping.
+    pub fn token_tenant(&self, claims: &AccessClaims) -> Option<Uuid> {
+        if claims.iss == self.config.issuer {
+            return claims.tid.as_deref().and_then(|tid| Uuid::parse_str(tid).ok());
+        }
+        let issuer = self.config.external_issuers.iter().find(|issuer| issuer.issuer == claims.iss)?;
+        claims
+            .tid
+            .as_ref()
+            .and_then(|tid| issuer.tenants.get(tid).copied())
+            .or(issuer.tenant_id)
+    }
+
+    /// Validate signature, expiry, issuer and audience of an access token.
+    /// Tokens from external issuers are verified against cached JWKS keys.
+    pub fn validate_access_token(&self, token: &str) -> Result<AccessClaims, HimsError> {
+        let header = decode_header(token).map_err(Self::invalid_token)?;
+
+        let claims = match header.kid.as_deref() {
+            Some(kid) if kid != self.config.key_id => self.validate_external(token, kid, header.alg)?,
+            _ => {
+                if header.alg != self.algorithm {
+                    return Err(Self::invalid_token("unexpected signing algorithm"));
+                }
+                let validation = self.validation(self.algorithm, &self.config.issuer, &self.config.audience);
+                decode::<AccessClaims>(token, &self.decoding_key, &validation)
+                    .map_err(Self::invalid_token)?
+                    .claims
+            }
+        };
+
+        if self.is_revoked(&claims.jti) {
+            return Err(HimsError::AuthenticationError {
+                message: "Token has been revoked".to_string(),
+            });
+        }
+
+        Ok(claims)
+    }
+
+    /// Whether a key ID is present in the JWKS cache
+    pub fn has_external_key(&self, kid: &str) -> bool {
+        self.jwks.keys.read().map(|keys| keys.contains_key(kid)).unwrap_or(false)
+    }
+
+    /// Fetch JWKS documents from all configured external issuers.
+    /// Returns false without fetching when called again within the minimum refresh interval.
+    pub async fn refresh_jwks(&self, client: &reqwest::Client) -> Result<bool, HimsError> {
+        if let Ok(last_refresh) = self.jwks.last_refresh.read() {
+            if let Some(at) = *last_refresh {
+                if at.elapsed() < Duration::from_secs(self.config.jwks_min_refresh_secs) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        let mut discovered = HashMap::new();
+        for issuer in &self.config.external_issuers {
+            let jwks: JwkSet = client
+                .get(&issuer.jwks_uri)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| HimsError::NetworkError { message: format!("JWKS fetch failed for {}: {}", issuer.issuer, e) })?
+                .json()
+                .await
+                .map_err(|e| HimsError::NetworkError { message: format!("Invalid JWKS from {}: {}", issuer.issuer, e) })?;
+
+            for jwk in &jwks.keys {
+                let Some(kid) = jwk.common.key_id.clone() else {
+                    continue;
+                };
+                match DecodingKey::from_jwk(jwk) {
+                    Ok(key) => {
+                        discovered.insert(kid, ExternalKey {
+                            issuer: issuer.issuer.clone(),
+                            audience: issuer.audience.clone(),
+                            key,
+                            params: jwk.algorithm.clone(),
+                        });
+                    }
+                    Err(e) => tracing::warn!("Skipping unusable JWK {} from {}: {}", kid, issuer.issuer, e),
+                }
+            }
+        }
+
+        tracing::info!("Loaded {} external signing keys", discovered.len());
+
+        if let Ok(mut keys) = self.jwks.keys.write() {
+            *keys = discovered;
+        }
+        if let Ok(mut last_refresh) = self.jwks.last_refresh.write() {
+            *last_refresh = Some(Instant::now());
+        }
+        Ok(true)
+    }
+
+    /// Add an access token ID to the in-memory revocation list
+    pub fn revoke(&self, jti: &str, expires_at: i64) {
+        if let Ok(mut revoked) = self.revoked.write() {
+            revoked.insert(jti.to_string(), expires_at);
+        }
+    }
+
+    /// Drop revocation entries for tokens that have expired anyway
+    pub fn prune_revocations(&self) {
+        let now = Utc::now().timestamp();
+        if let Ok(mut revoked) = self.revoked.write() {
+            revoked.retain(|_, expires_at| *expires_at > now);
+        }
+    }
+
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        !jti.is_empty() && self.revoked.read().map(|revoked| revoked.contains_key(jti)).unwrap_or(false)
+    }
+
+    /// Refresh tokens are stored hashed; only the client holds the raw value
+    pub fn hash_refresh_token(token: &str) -> String {
+        let digest = Sha256::digest(token.as_bytes());
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn generate_refresh_token(&self) -> Result<String, HimsError> {
+        let mut bytes = [0u8; 32];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| HimsError::InternalError { message: "Failed to generate refresh token".to_string() })?;
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    fn validate_external(&self, token: &str, kid: &str, alg: Algorithm) -> Result<AccessClaims, HimsError> {
+        let keys = self.jwks.keys.read()
+            .map_err(|_| Self::invalid_token("key cache unavailable"))?;
+        let key = keys.get(kid).ok_or_else(|| Self::invalid_token("unknown signing key"))?;
+
+        // Only accept asymmetric algorithms matching the published key type,
+        // which rules out HMAC confusion with a public key as the secret
+        let compatible = matches!(
+            (&key.params, alg),
+            (AlgorithmParameters::RSA(_), Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512
+                | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512)
+            | (AlgorithmParameters::EllipticCurve(_), Algorithm::ES256 | Algorithm::ES384)
+        );
+        if !compatible {
+            return Err(Self::invalid_token("algorithm does not match signing key"));
+        }
+
+        let validation = self.validation(alg, &key.issuer, &key.audience);
+        Ok(decode::<AccessClaims>(token, &key.key, &validation)
+            .map_err(Self::invalid_token)?
+            .claims)
+    }
+
+    fn validation(&self, alg: Algorithm, issuer: &str, audience: &str) -> Validation {
+        let mut validation = Validation::new(alg);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        validation.validate_nbf = true;
+        validation.leeway = self.config.leeway_secs;
+        validation
+    }
+
+    fn invalid_token(e: impl std::fmt::Display) -> HimsError {
+        HimsError::AuthenticationError {
+            message: format!("Invalid token: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> JwtManager {
+        JwtManager::from_config(JwtConfig {
+            private_key_pem: None,
+            public_key_pem: None,
+            external_issuers: vec![],
+            ..JwtConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn user() -> AuthenticatedUser {
+        AuthenticatedUser {
+            id: Uuid::new_v4().to_string(),
+            username: "clinician".to_string(),
+            role: "physician".to_string(),
+            permissions: vec!["read:patients".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_issue_and_validate_round_trip() {
+        let manager = manager();
+        let user = user();
+        let tokens = manager.issue_tokens(&user, Uuid::new_v4()).unwrap();
+
+        let claims = manager.validate_access_token(&tokens.access_token).unwrap();
+        assert_eq!(claims.sub, user.id);
+        assert_eq!(claims.to_user().permissions, user.permissions);
+    }
+
+    #[test]
+    fn test_rejects_foreign_signature_and_revoked_tokens() {
+        let manager = manager();
+        let tokens = manager.issue_tokens(&user(), Uuid::new_v4()).unwrap();
+
+        // A token signed by a different ephemeral key must not validate
+        assert!(self::manager().validate_access_token(&tokens.access_token).is_err());
+
+        manager.revoke(&tokens.access_jti, Utc::now().timestamp() + 60);
+        assert!(manager.validate_access_token(&tokens.access_token).is_err());
+    }
+
+    #[test]
+    fn test_token_tenant_binding() {
+        let hims_tenant = Uuid::new_v4();
+        let mapped_tenant = Uuid::new_v4();
+        let fallback_tenant = Uuid::new_v4();
+        let directory_id = Uuid::new_v4().to_string();
+        let issuers = format!(
+            r#"[{{"issuer":"https://login.microsoftonline.com/x/v2.0","jwks_uri":"https://idp/keys","audience":"hims",
+                "tenants":{{"{}":"{}"}}}},
+               {{"issuer":"https://keycloak/realms/clinic","jwks_uri":"https://kc/certs","audience":"hims",
+                "tenant_id":"{}"}}]"#,
+            directory_id, mapped_tenant, fallback_tenant
+        );
+        let manager = JwtManager::from_config(JwtConfig {
+            private_key_pem: None,
+            public_key_pem: None,
+            external_issuers: serde_json::from_str(&issuers).unwrap(),
+            ..JwtConfig::default()
+        })
+        .unwrap();
+
+        let tokens = manager.issue_tokens(&user(), Uuid::new_v4()).unwrap();
+        let mut claims = manager.validate_access_token(&tokens.access_token).unwrap();
+        claims.tid = Some(hims_tenant.to_string());
+        assert_eq!(manager.token_tenant(&claims), Some(hims_tenant));
+
+        // A directory ID is not a HIMS tenant unless the issuer maps it
+        claims.iss = "https://login.microsoftonline.com/x/v2.0".to_string();
+        assert_eq!(manager.token_tenant(&claims), None);
+        claims.tid = Some(directory_id);
+        assert_eq!(manager.token_tenant(&claims), Some(mapped_tenant));
+
+        claims.iss = "https://keycloak/realms/clinic".to_string();
+        claims.tid = None;
+        assert_eq!(manager.token_tenant(&claims), Some(fallback_tenant));
+
+        claims.iss = "https://unknown".to_string();
+        assert_eq!(manager.token_tenant(&claims), None);
+    }
+}
@@ -0,0 +1,277 @@
+use base64::{engine::general_purpose, Engine as _};
+use ring::hmac;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::HimsError;
+
+/// TOTP time step in seconds (RFC 6238 default)
+pub const TOTP_STEP_SECS: u64 = 30;
+/// Number of digits in a TOTP code
+pub const TOTP_DIGITS: u32 = 6;
+/// COSE algorithm identifier for ES256, the only WebAuthn algorithm accepted
+pub const COSE_ALG_ES256: i64 = -7;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// DER prefix of a P-256 SubjectPublicKeyInfo; the uncompressed point follows
+const P256_SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+    0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// MFA factor types
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MfaFactorType {
+    Totp,
+    WebAuthn,
+}
+
+impl MfaFactorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MfaFactorType::Totp => "totp",
+            MfaFactorType::WebAuthn => "webauthn",
+        }
+    }
+}
+
+/// Relying party settings for WebAuthn
+#[derive(Debug, Clone)]
+pub struct WebAuthnConfig {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub origin: String,
+}
+
+impl Default for WebAuthnConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: std::env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string()),
+            rp_name: std::env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "Open HIMS".to_string()),
+            origin: std::env::var("WEBAUTHN_ORIGIN").unwrap_or_else(|_| "http://localhost:3000".to_string()),
+        }
+    }
+}
+
+/// Parsed fixed-length prefix of WebAuthn authenticator data
+#[derive(Debug, Clone)]
+pub struct AuthenticatorData {
+    pub rp_id_hash: [u8; 32],
+    pub flags: u8,
+    pub sign_count: u32,
+}
+
+impl AuthenticatorData {
+    const USER_PRESENT: u8 = 0x01;
+    const USER_VERIFIED: u8 = 0x04;
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, HimsError> {
+        if bytes.len() < 37 {
+            return Err(mfa_error("Authenticator data is too short"));
+        }
+        let mut rp_id_hash = [0u8; 32];
+        rp_id_hash.copy_from_slice(&bytes[..32]);
+
+        Ok(Self {
+            rp_id_hash,
+            flags: bytes[32],
+            sign_count: u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]),
+        })
+    }
+
+    pub fn user_present(&self) -> bool {
+        self.flags & Self::USER_PRESENT != 0
+    }
+
+    pub fn user_verified(&self) -> bool {
+        self.flags & Self::USER_VERIFIED != 0
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+/// Encode bytes as unpadded RFC 4648 base32, as used by authenticator apps
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    output
+}
+
+/// Decode unpadded or padded RFC 4648 base32, ignoring case and spaces
+pub fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Some(output)
+}
+
+/// HOTP value (RFC 4226) for a counter
+pub fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let digest = hmac::sign(&key, &counter.to_be_bytes());
+    let digest = digest.as_ref();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | digest[offset + 3] as u32;
+
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// Verify a TOTP code within ±`window` steps of `now`. Steps at or before
+/// `last_used_step` are rejected to prevent replay. Returns the matched step.
+pub fn verify_totp(secret: &[u8], code: &str, now_unix: u64, window: u64, last_used_step: Option<u64>) -> Option<u64> {
+    let code: u32 = code.trim().parse().ok()?;
+    let current = now_unix / TOTP_STEP_SECS;
+
+    (current.saturating_sub(window)..=current + window)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| hotp(secret, *step) == code)
+}
+
+/// Provisioning URI rendered as a QR code by authenticator apps
+pub fn otpauth_uri(issuer: &str, account: &str, secret_base32: &str) -> String {
+    let encode = |s: &str| -> String {
+        s.bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    };
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        encode(issuer), encode(account), secret_base32, encode(issuer), TOTP_DIGITS, TOTP_STEP_SECS
+    )
+}
+
+/// Check a WebAuthn clientDataJSON against the expected ceremony, challenge and origin
+pub fn verify_client_data(
+    client_data_json: &[u8],
+    expected_type: &str,
+    expected_challenge: &str,
+    config: &WebAuthnConfig,
+) -> Result<(), HimsError> {
+    let client_data: ClientData = serde_json::from_slice(client_data_json)
+        .map_err(|e| mfa_error(&format!("Invalid client data: {}", e)))?;
+
+    if client_data.ceremony != expected_type {
+        return Err(mfa_error("Unexpected WebAuthn ceremony type"));
+    }
+    if client_data.challenge != expected_challenge {
+        return Err(mfa_error("WebAuthn challenge mismatch"));
+    }
+    if client_data.origin != config.origin {
+        return Err(mfa_error("WebAuthn origin mismatch"));
+    }
+    Ok(())
+}
+
+/// Check that authenticator data is scoped to our relying party and the user was present
+pub fn verify_authenticator_data(bytes: &[u8], config: &WebAuthnConfig) -> Result<AuthenticatorData, HimsError> {
+    let data = AuthenticatorData::parse(bytes)?;
+    let expected: [u8; 32] = Sha256::digest(config.rp_id.as_bytes()).into();
+
+    if data.rp_id_hash != expected {
+        return Err(mfa_error("Authenticator data is for a different relying party"));
+    }
+    if !data.user_present() {
+        return Err(mfa_error("User presence was not asserted"));
+    }
+    Ok(data)
+}
+
+/// Extract the uncompressed P-256 point from a SubjectPublicKeyInfo, as
+/// returned by `AuthenticatorAttestationResponse.getPublicKey()`
+pub fn p256_public_key_from_spki(spki: &[u8]) -> Result<Vec<u8>, HimsError> {
+    if spki.len() != P256_SPKI_PREFIX.len() + 65 || spki[..P256_SPKI_PREFIX.len()] != P256_SPKI_PREFIX {
+        return Err(mfa_error("Only ES256 (P-256) credentials are supported"));
+    }
+    Ok(spki[P256_SPKI_PREFIX.len()..].to_vec())
+}
+
+/// Verify an assertion signature over authenticatorData || SHA-256(clientDataJSON)
+pub fn verify_assertion_signature(
+    public_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<(), HimsError> {
+    let mut signed = authenticator_data.to_vec();
+    signed.extend_from_slice(&Sha256::digest(client_data_json));
+
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, public_key)
+        .verify(&signed, signature)
+        .map_err(|_| mfa_error("Invalid WebAuthn signature"))
+}
+
+/// Decode base64url fields sent by browsers
+pub fn decode_base64url(value: &str) -> Result<Vec<u8>, HimsError> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| mfa_error("Invalid base64url value"))
+}
+
+fn mfa_error(message: &str) -> HimsError {
+    HimsError::AuthenticationError {
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_matches_rfc6238_vector() {
+        // RFC 6238 appendix B, SHA-1 secret, T = 59s -> 94287082 (8 digits)
+        let secret = b"12345678901234567890";
+        assert_eq!(hotp(secret, 59 / TOTP_STEP_SECS), 287082);
+        assert_eq!(verify_totp(secret, "287082", 59, 1, None), Some(1));
+        assert_eq!(verify_totp(secret, "287082", 59, 1, Some(1)), None);
+    }
+
+    #[test]
+    fn test_base32_round_trip() {
+        let bytes = b"12345678901234567890";
+        let encoded = base32_encode(bytes);
+        assert_eq!(encoded, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_decode(&encoded.to_lowercase()).unwrap(), bytes.to_vec());
+    }
+}
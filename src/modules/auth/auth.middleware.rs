@@ -7,6 +7,25 @@ use axum::{
 use std::sync::Arc;
 
 use crate::modules::auth::{AuthService, AuthenticatedUser};
+use crate::modules::service_account::ServicePrincipal;
+use crate::utils::auth::extract_dev_user;
+
+/// Routes answered without a principal
+const PUBLIC_PATHS: &[&str] = &[
+    "/api/v1/auth/login",
+    "/api/v1/auth/validate",
+    "/api/v1/auth/refresh",
+    "/api/v1/auth/revoke",
+    "/api/v1/auth/password/change",
+    "/api/v1/auth/password/check",
+    "/api/v1/auth/workstation/challenge",
+    "/api/v1/auth/workstation/login",
+    "/api/v1/service-accounts/token",
+];
+
+/// Route prefixes answered without a principal; SSO sign-in happens
+/// before the browser holds a token
+const PUBLIC_PREFIXES: &[&str] = &["/api/v1/auth/sso/"];
 
 /// Authentication middleware
 pub struct AuthMiddleware {
@@ -18,24 +37,36 @@ impl AuthMiddleware {
         Self { auth_service }
     }
 
-    /// Middleware function to validate authentication
+    /// The service verifying tokens, which `authenticate` takes as its state
+    pub fn service(&self) -> Arc<AuthService> {
+        self.auth_service.clone()
+    }
+
+    /// Whether a path is answered without a principal: sign-in, token
+    /// exchange and the SSO redirects
+    pub fn is_public(path: &str) -> bool {
+        PUBLIC_PATHS.contains(&path) || PUBLIC_PREFIXES.iter().any(|prefix| path.starts_with(prefix))
+    }
+
+    /// Reject requests without a verified principal with 401. A bearer token
+    /// must pass signature, expiry and revocation checks; `X-User-ID` is
+    /// accepted only when `HIMS_DEV_USER_HEADER` is enabled.
     pub async fn authenticate(
         State(auth_service): State<Arc<AuthService>>,
         headers: HeaderMap,
         mut request: Request,
         next: Next,
     ) -> Result<Response, StatusCode> {
+        // API keys are verified by the service account layer outside this one
+        if Self::is_public(request.uri().path()) || request.extensions().get::<ServicePrincipal>().is_some() {
+            return Ok(next.run(request).await);
+        }
+
         // Extract Bearer token from Authorization header
         let token = headers
             .get("authorization")
             .and_then(|header| header.to_str().ok())
-            .and_then(|auth_header| {
-                if auth_header.starts_with("Bearer ") {
-                    Some(&auth_header[7..])
-                } else {
-                    None
-                }
-            });
+            .and_then(|auth_header| auth_header.strip_prefix("Bearer "));
 
         if let Some(token) = token {
             match auth_service.validate_token(token).await {
@@ -53,6 +84,8 @@ impl AuthMiddleware {
                     Err(StatusCode::INTERNAL_SERVER_ERROR)
                 }
             }
+        } else if extract_dev_user(&headers).is_some() {
+            Ok(next.run(request).await)
         } else {
             tracing::warn!("No authorization header provided");
             Err(StatusCode::UNAUTHORIZED)
@@ -86,4 +119,20 @@ impl AuthMiddleware {
             })
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_paths() {
+        assert!(AuthMiddleware::is_public("/api/v1/auth/login"));
+        assert!(AuthMiddleware::is_public("/api/v1/auth/sso/keycloak/callback"));
+        assert!(AuthMiddleware::is_public("/api/v1/service-accounts/token"));
+        assert!(!AuthMiddleware::is_public("/api/v1/auth/sessions"));
+        assert!(!AuthMiddleware::is_public("/api/v1/auth/login/../../patients"));
+        assert!(!AuthMiddleware::is_public("/api/v1/patients"));
+        assert!(!AuthMiddleware::is_public("/api/v1/service-accounts"));
+    }
+}
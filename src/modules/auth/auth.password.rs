@@ -0,0 +1,230 @@
+use base64::{engine::general_purpose, Engine as _};
+use ring::digest;
+use ring::pbkdf2;
+use serde::Serialize;
+use std::num::NonZeroU32;
+
+use crate::core::HimsError;
+
+/// PBKDF2-HMAC-SHA256 iterations for new hashes (OWASP 2023 guidance)
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
+const HASH_LEN: usize = 32;
+const HASH_PREFIX: &str = "$pbkdf2-sha256$";
+
+/// Password complexity, expiry and reuse rules
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Passwords older than this must be changed; 0 disables expiry
+    pub max_age_days: i64,
+    /// Number of previous passwords that may not be reused
+    pub history_count: i64,
+    /// Reject passwords found in the Have I Been Pwned corpus
+    pub check_breached: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        let env_num = |name: &str, default: i64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            min_length: env_num("PASSWORD_MIN_LENGTH", 12) as usize,
+            max_length: 128,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: false,
+            max_age_days: env_num("PASSWORD_MAX_AGE_DAYS", 90),
+            history_count: env_num("PASSWORD_HISTORY_COUNT", 5),
+            check_breached: std::env::var("PASSWORD_CHECK_BREACHED")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// Progressive lockout after repeated failed logins
+#[derive(Debug, Clone)]
+pub struct LockoutPolicy {
+    /// Failed attempts before the account locks
+    pub max_failed_attempts: i32,
+    /// Duration of the first lockout
+    pub base_lockout_secs: i64,
+    /// Upper bound for lockout duration
+    pub max_lockout_secs: i64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            max_failed_attempts: 5,
+            base_lockout_secs: 5 * 60,
+            max_lockout_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+impl LockoutPolicy {
+    /// Lockout duration doubles with each successive lockout
+    pub fn lockout_duration_secs(&self, previous_lockouts: i32) -> i64 {
+        let factor = 1i64 << previous_lockouts.clamp(0, 16);
+        (self.base_lockout_secs * factor).min(self.max_lockout_secs)
+    }
+}
+
+/// A password policy rule that was not met
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordViolation {
+    TooShort { min_length: usize },
+    TooLong { max_length: usize },
+    MissingUppercase,
+    MissingLowercase,
+    MissingDigit,
+    MissingSymbol,
+    ContainsUsername,
+    RecentlyUsed,
+    Breached { occurrences: u64 },
+}
+
+impl PasswordPolicy {
+    /// Check complexity rules. Reuse and breach checks need storage/network
+    /// and are done by `AuthService`.
+    pub fn validate(&self, password: &str, username: &str) -> Vec<PasswordViolation> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort { min_length: self.min_length });
+        }
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong { max_length: self.max_length });
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            violations.push(PasswordViolation::MissingUppercase);
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            violations.push(PasswordViolation::MissingLowercase);
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PasswordViolation::MissingDigit);
+        }
+        if self.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+            violations.push(PasswordViolation::MissingSymbol);
+        }
+        if username.len() >= 3 && password.to_lowercase().contains(&username.to_lowercase()) {
+            violations.push(PasswordViolation::ContainsUsername);
+        }
+
+        violations
+    }
+}
+
+/// Hash a password as `$pbkdf2-sha256$<iterations>$<salt>$<hash>`
+pub fn hash_password(password: &str, salt: &[u8]) -> String {
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations");
+    let mut hash = [0u8; HASH_LEN];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, password.as_bytes(), &mut hash);
+
+    format!(
+        "{}{}${}${}",
+        HASH_PREFIX,
+        PBKDF2_ITERATIONS,
+        general_purpose::STANDARD_NO_PAD.encode(salt),
+        general_purpose::STANDARD_NO_PAD.encode(hash)
+    )
+}
+
+/// Verify a password against a stored hash in constant time
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let Some(encoded) = stored.strip_prefix(HASH_PREFIX) else {
+        return false;
+    };
+    let mut parts = encoded.splitn(3, '$');
+    let (Some(iterations), Some(salt), Some(hash)) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    let (Some(iterations), Ok(salt), Ok(hash)) = (
+        iterations.parse::<u32>().ok().and_then(NonZeroU32::new),
+        general_purpose::STANDARD_NO_PAD.decode(salt),
+        general_purpose::STANDARD_NO_PAD.decode(hash),
+    ) else {
+        return false;
+    };
+
+    pbkdf2::verify(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, password.as_bytes(), &hash).is_ok()
+}
+
+/// Split the uppercase SHA-1 of a password into the 5-character prefix sent
+/// to the HIBP range API and the suffix matched locally (k-anonymity)
+pub fn hibp_range(password: &str) -> (String, String) {
+    let hash: String = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, password.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect();
+    let (prefix, suffix) = hash.split_at(5);
+    (prefix.to_string(), suffix.to_string())
+}
+
+/// Find the breach count for a hash suffix in an HIBP range response
+pub fn hibp_occurrences(response: &str, suffix: &str) -> u64 {
+    response
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Convert policy violations into a validation error
+pub fn violations_error(violations: &[PasswordViolation]) -> HimsError {
+    HimsError::ValidationError {
+        message: format!(
+            "Password does not meet policy: {}",
+            serde_json::to_string(violations).unwrap_or_default()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_and_hashing() {
+        let policy = PasswordPolicy {
+            min_length: 12,
+            require_symbol: false,
+            ..PasswordPolicy::default()
+        };
+        assert_eq!(policy.validate("Correct-Horse-7-Battery", "jdoe"), vec![]);
+        assert!(policy.validate("jdoe2023", "jdoe").contains(&PasswordViolation::ContainsUsername));
+
+        let hash = hash_password("Correct-Horse-7-Battery", b"0123456789abcdef");
+        assert!(verify_password("Correct-Horse-7-Battery", &hash));
+        assert!(!verify_password("correct-horse-7-battery", &hash));
+    }
+
+    #[test]
+    fn test_hibp_range_and_lockout() {
+        // SHA-1("password") = 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+        let (prefix, suffix) = hibp_range("password");
+        assert_eq!(prefix, "5BAA6");
+        assert_eq!(hibp_occurrences("1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\r\n00D4F:1", &suffix), 9545824);
+
+        let lockout = LockoutPolicy::default();
+        assert_eq!(lockout.lockout_duration_secs(0), 300);
+        assert_eq!(lockout.lockout_duration_secs(2), 1200);
+        assert_eq!(lockout.lockout_duration_secs(20), 24 * 60 * 60);
+    }
+}
@@ -1,75 +1,984 @@
 use anyhow::Result;
-use sqlx::PgPool;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, TimeZone, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 use crate::core::HimsError;
+use crate::modules::auth::auth_jwt::{install_token_verifier, JwtConfig, JwtManager, TokenPair};
+use crate::models::{AuditLog, AuditOutcome};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::auth_mfa::{self, MfaFactorType, WebAuthnConfig, COSE_ALG_ES256};
+use crate::modules::auth::auth_password::{self, LockoutPolicy, PasswordPolicy, PasswordViolation};
+use crate::modules::auth::risk_service::{RiskConfig, RiskEngine};
+use crate::modules::auth::session_service::{SessionClient, SessionConfig, SessionManager};
+use crate::modules::authorization::authorization_sql::sessions::MARK_SESSION_MFA_VERIFIED;
+use crate::modules::role::RoleService;
+
+// Import SQL queries from separate file
+use crate::modules::auth::auth_sql::*;
+
+/// How long a WebAuthn challenge stays valid
+const WEBAUTHN_CHALLENGE_TTL: Duration = Duration::from_secs(300);
+/// Accepted TOTP clock drift in steps either side of now
+const TOTP_WINDOW: u64 = 1;
+/// Have I Been Pwned k-anonymity range endpoint
+const HIBP_RANGE_URL: &str = "https://api.pwnedpasswords.com/range";
+/// Well-formed hash verified for unknown usernames to keep timing uniform
+const DUMMY_PASSWORD_HASH: &str =
+    "$pbkdf2-sha256$600000$AAAAAAAAAAAAAAAAAAAAAA$AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
 
 /// Authentication and authorization service
 pub struct AuthService {
     pool: PgPool,
+    jwt: Arc<JwtManager>,
+    http_client: reqwest::Client,
+    sessions: Arc<SessionManager>,
+    risk: RiskEngine,
+    role_service: RoleService,
+    audit_service: AuditService,
+    password_policy: PasswordPolicy,
+    lockout_policy: LockoutPolicy,
+    webauthn: WebAuthnConfig,
+    challenges: RwLock<HashMap<String, PendingChallenge>>,
+    rng: SystemRandom,
+}
+
+struct UserCredentials {
+    id: Uuid,
+    username: String,
+    password_hash: String,
+    role: String,
+    active: bool,
+    locked_until: Option<DateTime<Utc>>,
+    password_changed_at: DateTime<Utc>,
+    must_reset_password: bool,
+    lockout_count: i32,
+}
+
+struct PendingChallenge {
+    challenge: String,
+    created_at: Instant,
 }
 
 impl AuthService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        let jwt = JwtManager::from_config(JwtConfig::default()).unwrap_or_else(|e| {
+            tracing::error!("Invalid JWT configuration, falling back to an ephemeral key: {}", e);
+            JwtManager::from_config(JwtConfig {
+                private_key_pem: None,
+                public_key_pem: None,
+                ..JwtConfig::default()
+            })
+            .expect("failed to generate ephemeral JWT signing key")
+        });
+        Self::with_jwt(pool, Arc::new(jwt))
+    }
+
+    /// Create the service with an explicitly configured token manager
+    pub fn with_jwt(pool: PgPool, jwt: Arc<JwtManager>) -> Self {
+        install_token_verifier(jwt.clone());
+        Self {
+            sessions: Arc::new(SessionManager::new(pool.clone(), SessionConfig::default())),
+            risk: RiskEngine::new(pool.clone(), RiskConfig::default()),
+            role_service: RoleService::new(pool.clone()),
+            audit_service: AuditService::new(pool.clone()),
+            password_policy: PasswordPolicy::default(),
+            lockout_policy: LockoutPolicy::default(),
+            pool,
+            jwt,
+            http_client: reqwest::Client::new(),
+            webauthn: WebAuthnConfig::default(),
+            challenges: RwLock::new(HashMap::new()),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Token manager used for issuance and validation
+    pub fn jwt(&self) -> Arc<JwtManager> {
+        self.jwt.clone()
+    }
+
+    /// Session manager for login sessions
+    pub fn sessions(&self) -> Arc<SessionManager> {
+        self.sessions.clone()
     }
 
-    /// Authenticate user with credentials
+    /// Authenticate user with credentials. Locked accounts fail with
+    /// `SecurityError`; expired or flagged passwords fail with
+    /// `AuthenticationError` until changed.
     pub async fn authenticate(
         &self,
         username: &str,
         password: &str,
     ) -> Result<Option<AuthenticatedUser>, HimsError> {
-        // TODO: Implement actual authentication logic
-        // This is a placeholder implementation
-        
-        if username == "admin" && password == "admin123" {
-            Ok(Some(AuthenticatedUser {
-                id: Uuid::new_v4().to_string(),
-                username: username.to_string(),
-                role: "administrator".to_string(),
-                permissions: vec![
-                    "read:patients".to_string(),
-                    "write:patients".to_string(),
-                    "read:appointments".to_string(),
-                    "write:appointments".to_string(),
-                    "read:medical_records".to_string(),
-                    "write:medical_records".to_string(),
-                    "read:audit_logs".to_string(),
-                ],
-            }))
+        let Some(credentials) = self.get_credentials(username).await? else {
+            // Spend the same time as a real check so usernames cannot be probed
+            auth_password::verify_password(password, DUMMY_PASSWORD_HASH);
+            return Ok(None);
+        };
+
+        if !credentials.active {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        if let Some(locked_until) = credentials.locked_until.filter(|until| *until > now) {
+            self.audit(
+                AuditLog::authentication_event(Some(credentials.id), AuditOutcome::SeriousFailure)
+                    .with_details(format!("login_rejected_locked until {}", locked_until)),
+            )
+            .await;
+            return Err(HimsError::SecurityError {
+                message: format!("Account locked until {}", locked_until.to_rfc3339()),
+            });
+        }
+
+        if !auth_password::verify_password(password, &credentials.password_hash) {
+            self.record_failed_password(&credentials).await?;
+            return Ok(None);
+        }
+
+        sqlx::query(RECORD_SUCCESSFUL_LOGIN)
+            .bind(credentials.id)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let expired = self.password_policy.max_age_days > 0
+            && now - credentials.password_changed_at > chrono::Duration::days(self.password_policy.max_age_days);
+        if credentials.must_reset_password || expired {
+            self.audit(
+                AuditLog::authentication_event(Some(credentials.id), AuditOutcome::MinorFailure)
+                    .with_details("login_requires_password_reset".to_string()),
+            )
+            .await;
+            return Err(HimsError::AuthenticationError {
+                message: if expired { "Password expired" } else { "Password reset required" }.to_string(),
+            });
+        }
+
+        self.audit(
+            AuditLog::authentication_event(Some(credentials.id), AuditOutcome::Success)
+                .with_details("login_succeeded".to_string()),
+        )
+        .await;
+
+        let permissions = self
+            .role_service
+            .get_user_permissions(credentials.id)
+            .await?
+            .into_iter()
+            .map(|action| action.to_string())
+            .collect();
+
+        Ok(Some(AuthenticatedUser {
+            id: credentials.id.to_string(),
+            username: credentials.username,
+            role: credentials.role,
+            permissions,
+        }))
+    }
+
+    /// Check a candidate password against the policy and the breach corpus
+    pub async fn check_password(&self, username: &str, password: &str) -> Result<Vec<PasswordViolation>, HimsError> {
+        let mut violations = self.password_policy.validate(password, username);
+
+        if self.password_policy.check_breached {
+            let occurrences = self.breached_password_count(password).await?;
+            if occurrences > 0 {
+                violations.push(PasswordViolation::Breached { occurrences });
+            }
+        }
+        Ok(violations)
+    }
+
+    /// Number of times a password appears in Have I Been Pwned. Only the first
+    /// five characters of its SHA-1 leave the server (k-anonymity range API).
+    pub async fn breached_password_count(&self, password: &str) -> Result<u64, HimsError> {
+        let (prefix, suffix) = auth_password::hibp_range(password);
+
+        let response = self
+            .http_client
+            .get(format!("{}/{}", HIBP_RANGE_URL, prefix))
+            .header("Add-Padding", "true")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        // Fail open: an unavailable breach API must not block password changes
+        match response {
+            Ok(response) => {
+                let body = response
+                    .text()
+                    .await
+                    .map_err(|e| HimsError::NetworkError { message: e.to_string() })?;
+                Ok(auth_password::hibp_occurrences(&body, &suffix))
+            }
+            Err(e) => {
+                tracing::warn!("Breached password check unavailable: {}", e);
+                Ok(0)
+            }
+        }
+    }
+
+    /// Change a password after verifying the current one. This is also how a
+    /// forced or expiry-driven reset is completed. Other sessions are ended.
+    pub async fn change_password(
+        &self,
+        username: &str,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), HimsError> {
+        let credentials = self.get_credentials(username).await?.filter(|c| c.active).ok_or_else(|| {
+            HimsError::AuthenticationError { message: "Invalid credentials".to_string() }
+        })?;
+
+        if credentials.locked_until.is_some_and(|until| until > Utc::now()) {
+            return Err(HimsError::SecurityError { message: "Account is locked".to_string() });
+        }
+        if !auth_password::verify_password(current_password, &credentials.password_hash) {
+            self.record_failed_password(&credentials).await?;
+            return Err(HimsError::AuthenticationError { message: "Invalid credentials".to_string() });
+        }
+
+        let mut violations = self.check_password(username, new_password).await?;
+        if self.is_recently_used(credentials.id, new_password, &credentials.password_hash).await? {
+            violations.push(PasswordViolation::RecentlyUsed);
+        }
+        if !violations.is_empty() {
+            return Err(auth_password::violations_error(&violations));
+        }
+
+        self.set_password(credentials.id, &credentials.password_hash, new_password).await?;
+        self.logout_everywhere(credentials.id, "password_changed").await?;
+
+        self.audit(
+            AuditLog::authentication_event(Some(credentials.id), AuditOutcome::Success)
+                .with_details("password_changed".to_string()),
+        )
+        .await;
+        tracing::info!("Password changed for user {}", credentials.id);
+        Ok(())
+    }
+
+    /// Clear a lockout (administrator)
+    pub async fn unlock_user(&self, actor: Uuid, user_id: Uuid) -> Result<(), HimsError> {
+        self.sessions.require_admin(actor).await?;
+
+        sqlx::query(UNLOCK_USER)
+            .bind(user_id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.audit(
+            AuditLog::authentication_event(Some(user_id), AuditOutcome::Success)
+                .with_details(format!("account_unlocked by {}", actor)),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Require a user to change their password at next login (administrator).
+    /// Existing sessions are ended.
+    pub async fn force_password_reset(&self, actor: Uuid, user_id: Uuid) -> Result<(), HimsError> {
+        self.sessions.require_admin(actor).await?;
+
+        sqlx::query(SET_MUST_RESET_PASSWORD)
+            .bind(user_id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.logout_everywhere(user_id, "password_reset_forced").await?;
+
+        self.audit(
+            AuditLog::authentication_event(Some(user_id), AuditOutcome::Success)
+                .with_details(format!("password_reset_forced by {}", actor)),
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn get_credentials(&self, username: &str) -> Result<Option<UserCredentials>, HimsError> {
+        let row = sqlx::query(GET_USER_CREDENTIALS)
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.map(|row| UserCredentials {
+            id: row.get("id"),
+            username: row.get("username"),
+            password_hash: row.get("password_hash"),
+            role: row.get("role"),
+            active: row.get("active"),
+            locked_until: row.get("locked_until"),
+            password_changed_at: row.get("password_changed_at"),
+            must_reset_password: row.get("must_reset_password"),
+            lockout_count: row.get("lockout_count"),
+        }))
+    }
+
+    /// Count a failed attempt and lock the account progressively
+    async fn record_failed_password(&self, credentials: &UserCredentials) -> Result<(), HimsError> {
+        let lock_until = Utc::now()
+            + chrono::Duration::seconds(self.lockout_policy.lockout_duration_secs(credentials.lockout_count));
+
+        let row = sqlx::query(RECORD_FAILED_PASSWORD)
+            .bind(credentials.id)
+            .bind(self.lockout_policy.max_failed_attempts)
+            .bind(lock_until)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        // The attempt counter restarts when the account locks
+        let failed_attempts: i32 = row.get("failed_login_attempts");
+        let locked_until: Option<DateTime<Utc>> = row.get("locked_until");
+        let newly_locked = failed_attempts == 0 && locked_until.is_some_and(|until| until > Utc::now());
+        let log = AuditLog::authentication_event(Some(credentials.id), AuditOutcome::MinorFailure)
+            .with_details("login_failed".to_string());
+
+        if newly_locked {
+            tracing::warn!("Account {} locked until {}", credentials.username, lock_until);
+            self.audit(
+                log.with_outcome(AuditOutcome::SeriousFailure)
+                    .with_details(format!("account_locked until {}", lock_until)),
+            )
+            .await;
         } else {
-            Ok(None)
+            self.audit(log).await;
+        }
+        Ok(())
+    }
+
+    async fn is_recently_used(&self, user_id: Uuid, password: &str, current_hash: &str) -> Result<bool, HimsError> {
+        if auth_password::verify_password(password, current_hash) {
+            return Ok(true);
+        }
+
+        let rows = sqlx::query(GET_PASSWORD_HISTORY)
+            .bind(user_id)
+            .bind(self.password_policy.history_count)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .any(|row| auth_password::verify_password(password, &row.get::<String, _>("password_hash"))))
+    }
+
+    async fn set_password(&self, user_id: Uuid, previous_hash: &str, new_password: &str) -> Result<(), HimsError> {
+        let mut salt = [0u8; 16];
+        self.rng
+            .fill(&mut salt)
+            .map_err(|_| HimsError::InternalError { message: "Failed to generate password salt".to_string() })?;
+        let hash = auth_password::hash_password(new_password, &salt);
+        let now = Utc::now();
+
+        sqlx::query(INSERT_PASSWORD_HISTORY)
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .bind(previous_hash)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(UPDATE_PASSWORD)
+            .bind(user_id)
+            .bind(&hash)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Audit failures must not block authentication
+    async fn audit(&self, log: AuditLog) {
+        if let Err(e) = self.audit_service.create_audit_log(&log).await {
+            tracing::error!("Failed to write authentication audit event: {}", e);
         }
     }
 
     /// Validate JWT token
     pub async fn validate_token(&self, token: &str) -> Result<Option<AuthenticatedUser>, HimsError> {
-        // TODO: Implement JWT validation
-        // This is a placeholder implementation
-        
-        if token.starts_with("valid_") {
-            Ok(Some(AuthenticatedUser {
-                id: "user-123".to_string(),
-                username: "test_user".to_string(),
-                role: "practitioner".to_string(),
-                permissions: vec![
-                    "read:patients".to_string(),
-                    "read:appointments".to_string(),
-                    "write:appointments".to_string(),
-                ],
-            }))
-        } else {
-            Ok(None)
+        // Tokens signed with a key we have not seen may come from an external
+        // IdP that rotated its keys; refresh JWKS once before rejecting.
+        if let Ok(header) = jsonwebtoken::decode_header(token) {
+            if let Some(kid) = header.kid.as_deref() {
+                if kid != self.jwt.config().key_id && !self.jwt.has_external_key(kid) {
+                    if let Err(e) = self.jwt.refresh_jwks(&self.http_client).await {
+                        tracing::warn!("JWKS refresh failed: {}", e);
+                    }
+                }
+            }
+        }
+
+        let claims = match self.jwt.validate_access_token(token) {
+            Ok(claims) => claims,
+            Err(e) => {
+                tracing::warn!("Token rejected: {}", e);
+                return Ok(None);
+            }
+        };
+
+        // The in-memory list only covers revocations made by this instance
+        if !claims.jti.is_empty() {
+            let revoked: bool = sqlx::query(IS_ACCESS_TOKEN_REVOKED)
+                .bind(&claims.jti)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+                .get("revoked");
+            if revoked {
+                self.jwt.revoke(&claims.jti, claims.exp);
+                return Ok(None);
+            }
+        }
+
+        // Tokens we issued are bound to a session that may have timed out or
+        // been logged out; external IdP tokens carry no session.
+        if let Some(sid) = claims.sid.as_deref() {
+            if claims.iss == self.jwt.config().issuer && self.sessions.touch(sid).await?.is_none() {
+                tracing::warn!("Token for ended session {} rejected", sid);
+                return Ok(None);
+            }
         }
+
+        Ok(Some(claims.to_user()))
     }
 
     /// Generate JWT token for authenticated user
     pub async fn generate_token(&self, user: &AuthenticatedUser) -> Result<String, HimsError> {
-        // TODO: Implement JWT generation
-        // This is a placeholder implementation
-        
-        Ok(format!("valid_token_for_{}", user.id))
+        Ok(self.issue_tokens(user, &SessionClient::default()).await?.access_token)
+    }
+
+    /// Start a risk-scored login session and issue an access token and a
+    /// persisted refresh token. The refresh token family doubles as the session ID.
+    pub async fn issue_tokens(&self, user: &AuthenticatedUser, client: &SessionClient) -> Result<TokenPair, HimsError> {
+        let user_id = Uuid::parse_str(&user.id).map_err(|_| HimsError::ValidationError {
+            message: format!("Invalid user ID: {}", user.id),
+        })?;
+        let family_id = Uuid::new_v4();
+        let session_id = family_id.to_string();
+
+        let assessment = self.risk.assess_login(user_id, &user.username, client).await?;
+        self.sessions.start_session(user_id, &session_id, client, assessment.score).await?;
+        self.risk
+            .record_login(Some(user_id), &user.username, Some(&session_id), client, true, Some(&assessment))
+            .await?;
+
+        self.issue_in_family(user, family_id).await
+    }
+
+    /// Record a failed login attempt; recent failures raise the risk of the next login
+    pub async fn record_failed_login(&self, username: &str, client: &SessionClient) -> Result<(), HimsError> {
+        self.risk.record_login(None, username, None, client, false, None).await
+    }
+
+    /// Exchange a refresh token for a new token pair, rotating the refresh token.
+    /// Reuse of an already rotated token revokes the whole family.
+    pub async fn refresh_tokens(&self, refresh_token: &str) -> Result<Option<TokenPair>, HimsError> {
+        let token_hash = JwtManager::hash_refresh_token(refresh_token);
+        let Some(row) = sqlx::query(GET_REFRESH_TOKEN)
+            .bind(&token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+
+        let id: Uuid = row.get("id");
+        let family_id: Uuid = row.get("family_id");
+        let expires_at: DateTime<Utc> = row.get("expires_at");
+        let rotated_at: Option<DateTime<Utc>> = row.get("rotated_at");
+        let revoked_at: Option<DateTime<Utc>> = row.get("revoked_at");
+
+        if revoked_at.is_some() || expires_at <= Utc::now() {
+            return Ok(None);
+        }
+
+        if rotated_at.is_some() {
+            tracing::warn!("Refresh token reuse detected for family {}; revoking family", family_id);
+            self.revoke_family(family_id, "reuse_detected").await?;
+            return Ok(None);
+        }
+
+        if self.sessions.touch(&family_id.to_string()).await?.is_none() {
+            self.revoke_family(family_id, "session_ended").await?;
+            return Ok(None);
+        }
+
+        let rotated = sqlx::query(ROTATE_REFRESH_TOKEN)
+            .bind(id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        // Lost a race with a concurrent refresh of the same token
+        if rotated.rows_affected() == 0 {
+            self.revoke_family(family_id, "reuse_detected").await?;
+            return Ok(None);
+        }
+
+        let user: AuthenticatedUser = serde_json::from_value(row.get("user_claims"))
+            .map_err(|e| HimsError::InternalError { message: e.to_string() })?;
+
+        self.issue_in_family(&user, family_id).await.map(Some)
+    }
+
+    /// Revoke an access token before it expires
+    pub async fn revoke_access_token(&self, token: &str, reason: &str) -> Result<(), HimsError> {
+        let claims = self.jwt.validate_access_token(token)?;
+        let expires_at = Utc.timestamp_opt(claims.exp, 0).single().unwrap_or_else(Utc::now);
+
+        sqlx::query(INSERT_REVOKED_ACCESS_TOKEN)
+            .bind(&claims.jti)
+            .bind(&claims.sub)
+            .bind(expires_at)
+            .bind(Utc::now())
+            .bind(reason)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.jwt.revoke(&claims.jti, claims.exp);
+        tracing::info!("Access token {} revoked for user {}", claims.jti, claims.sub);
+        Ok(())
+    }
+
+    /// Revoke a refresh token and every token rotated from the same login
+    pub async fn revoke_refresh_token(&self, refresh_token: &str, reason: &str) -> Result<(), HimsError> {
+        let token_hash = JwtManager::hash_refresh_token(refresh_token);
+        let row = sqlx::query(GET_REFRESH_TOKEN)
+            .bind(&token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        if let Some(row) = row {
+            let family_id: Uuid = row.get("family_id");
+            self.revoke_family(family_id, reason).await?;
+            self.sessions.end_session(&family_id.to_string()).await?;
+        }
+        Ok(())
+    }
+
+    /// End every session of a user and revoke their refresh tokens. Access
+    /// tokens stop validating because their session is gone.
+    pub async fn logout_everywhere(&self, user_id: Uuid, reason: &str) -> Result<u64, HimsError> {
+        self.revoke_all_for_user(&user_id.to_string(), reason).await?;
+        self.sessions.end_all_sessions(user_id).await
+    }
+
+    /// End one session and revoke its refresh token family
+    pub async fn logout_session(&self, session_id: &str, reason: &str) -> Result<(), HimsError> {
+        if let Ok(family_id) = Uuid::parse_str(session_id) {
+            self.revoke_family(family_id, reason).await?;
+        }
+        self.sessions.end_session(session_id).await
+    }
+
+    /// Revoke all refresh tokens held by a user
+    pub async fn revoke_all_for_user(&self, user_id: &str, reason: &str) -> Result<u64, HimsError> {
+        let result = sqlx::query(REVOKE_USER_REFRESH_TOKENS)
+            .bind(user_id)
+            .bind(Utc::now())
+            .bind(reason)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Load persisted revocations into the in-memory list, e.g. at startup
+    pub async fn load_revocations(&self) -> Result<usize, HimsError> {
+        let rows = sqlx::query(GET_ACTIVE_REVOCATIONS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        for row in &rows {
+            let expires_at: DateTime<Utc> = row.get("expires_at");
+            self.jwt.revoke(&row.get::<String, _>("jti"), expires_at.timestamp());
+        }
+        Ok(rows.len())
+    }
+
+    /// Delete expired refresh tokens and revocation entries
+    pub async fn cleanup_expired_tokens(&self) -> Result<i64, HimsError> {
+        self.jwt.prune_revocations();
+        let row = sqlx::query(DELETE_EXPIRED_TOKENS)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.get("deleted"))
+    }
+
+    async fn issue_in_family(&self, user: &AuthenticatedUser, family_id: Uuid) -> Result<TokenPair, HimsError> {
+        let tokens = self.jwt.issue_tokens(user, family_id)?;
+
+        sqlx::query(INSERT_REFRESH_TOKEN)
+            .bind(Uuid::new_v4())
+            .bind(family_id)
+            .bind(&user.id)
+            .bind(JwtManager::hash_refresh_token(&tokens.refresh_token))
+            .bind(serde_json::to_value(user).unwrap_or_default())
+            .bind(tokens.refresh_expires_at)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(tokens)
+    }
+
+    async fn revoke_family(&self, family_id: Uuid, reason: &str) -> Result<(), HimsError> {
+        sqlx::query(REVOKE_REFRESH_TOKEN_FAMILY)
+            .bind(family_id)
+            .bind(Utc::now())
+            .bind(reason)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Start TOTP enrollment. The factor is unusable until confirmed with a code.
+    pub async fn enroll_totp(&self, user_id: Uuid, account_name: &str) -> Result<TotpEnrollment, HimsError> {
+        let mut secret = [0u8; 20];
+        self.rng
+            .fill(&mut secret)
+            .map_err(|_| HimsError::InternalError { message: "Failed to generate TOTP secret".to_string() })?;
+        let secret = auth_mfa::base32_encode(&secret);
+        let factor_id = Uuid::new_v4();
+
+        sqlx::query(INSERT_MFA_FACTOR)
+            .bind(factor_id)
+            .bind(user_id.to_string())
+            .bind(MfaFactorType::Totp.as_str())
+            .bind("Authenticator app")
+            .bind(&secret)
+            .bind(None::<String>)
+            .bind(None::<Vec<u8>>)
+            .bind(0i64)
+            .bind(None::<DateTime<Utc>>)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(TotpEnrollment {
+            factor_id,
+            otpauth_uri: auth_mfa::otpauth_uri(&self.webauthn.rp_name, account_name, &secret),
+            secret,
+        })
+    }
+
+    /// Confirm TOTP enrollment with the first code from the authenticator app
+    pub async fn confirm_totp(&self, user_id: Uuid, factor_id: Uuid, code: &str) -> Result<bool, HimsError> {
+        let factors = self.get_mfa_factors(user_id).await?;
+        let Some(factor) = factors.iter().find(|f| f.id == factor_id && f.factor_type == MfaFactorType::Totp) else {
+            return Ok(false);
+        };
+        let Some(step) = Self::match_totp(factor, code) else {
+            return Ok(false);
+        };
+
+        let result = sqlx::query(CONFIRM_MFA_FACTOR)
+            .bind(factor_id)
+            .bind(user_id.to_string())
+            .bind(Utc::now())
+            .bind(step as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Verify a TOTP code against the user's confirmed factors and mark the session verified
+    pub async fn verify_totp(&self, user_id: Uuid, code: &str, session_id: Option<&str>) -> Result<bool, HimsError> {
+        for factor in self.get_mfa_factors(user_id).await? {
+            if factor.factor_type != MfaFactorType::Totp || factor.confirmed_at.is_none() {
+                continue;
+            }
+            let Some(step) = Self::match_totp(&factor, code) else {
+                continue;
+            };
+
+            // Conditional update so a concurrently replayed code is rejected
+            let used = sqlx::query(USE_TOTP_STEP)
+                .bind(factor.id)
+                .bind(step as i64)
+                .bind(Utc::now())
+                .execute(&self.pool)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+            if used.rows_affected() == 1 {
+                self.mark_mfa_verified(user_id, session_id).await?;
+                return Ok(true);
+            }
+        }
+
+        tracing::warn!("TOTP verification failed for user {}", user_id);
+        Ok(false)
+    }
+
+    /// Create options for `navigator.credentials.create()`
+    pub fn begin_webauthn_registration(&self, user_id: Uuid, user_name: &str) -> Result<WebAuthnCreationOptions, HimsError> {
+        let challenge = self.issue_challenge("register", user_id)?;
+
+        Ok(WebAuthnCreationOptions {
+            challenge,
+            rp_id: self.webauthn.rp_id.clone(),
+            rp_name: self.webauthn.rp_name.clone(),
+            user_id: general_purpose::URL_SAFE_NO_PAD.encode(user_id.as_bytes()),
+            user_name: user_name.to_string(),
+            algorithms: vec![COSE_ALG_ES256],
+            timeout_ms: WEBAUTHN_CHALLENGE_TTL.as_millis() as u64,
+        })
+    }
+
+    /// Store a credential created by the browser after checking the registration ceremony
+    pub async fn finish_webauthn_registration(
+        &self,
+        user_id: Uuid,
+        registration: WebAuthnRegistration,
+    ) -> Result<MfaFactor, HimsError> {
+        let challenge = self.take_challenge("register", user_id)?;
+
+        if registration.public_key_algorithm != COSE_ALG_ES256 {
+            return Err(HimsError::ValidationError {
+                message: "Only ES256 WebAuthn credentials are supported".to_string(),
+            });
+        }
+
+        let client_data = auth_mfa::decode_base64url(&registration.client_data_json)?;
+        auth_mfa::verify_client_data(&client_data, "webauthn.create", &challenge, &self.webauthn)?;
+        let authenticator_data = auth_mfa::decode_base64url(&registration.authenticator_data)?;
+        let data = auth_mfa::verify_authenticator_data(&authenticator_data, &self.webauthn)?;
+        let public_key = auth_mfa::p256_public_key_from_spki(&auth_mfa::decode_base64url(&registration.public_key)?)?;
+
+        let now = Utc::now();
+        let factor = MfaFactor {
+            id: Uuid::new_v4(),
+            factor_type: MfaFactorType::WebAuthn,
+            name: registration.name.or_else(|| Some("Security key".to_string())),
+            credential_id: Some(registration.credential_id),
+            confirmed_at: Some(now),
+            last_used_at: None,
+            created_at: now,
+            totp_secret: None,
+            last_used_step: None,
+            public_key: Some(public_key),
+            sign_count: data.sign_count as i64,
+        };
+
+        sqlx::query(INSERT_MFA_FACTOR)
+            .bind(factor.id)
+            .bind(user_id.to_string())
+            .bind(MfaFactorType::WebAuthn.as_str())
+            .bind(&factor.name)
+            .bind(None::<String>)
+            .bind(&factor.credential_id)
+            .bind(&factor.public_key)
+            .bind(factor.sign_count)
+            .bind(factor.confirmed_at)
+            .bind(factor.created_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("WebAuthn credential registered for user {}", user_id);
+        Ok(factor)
+    }
+
+    /// Create options for `navigator.credentials.get()`
+    pub async fn begin_webauthn_authentication(&self, user_id: Uuid) -> Result<WebAuthnRequestOptions, HimsError> {
+        let allow_credentials: Vec<String> = self
+            .get_mfa_factors(user_id)
+            .await?
+            .into_iter()
+            .filter(|f| f.factor_type == MfaFactorType::WebAuthn)
+            .filter_map(|f| f.credential_id)
+            .collect();
+
+        if allow_credentials.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "No WebAuthn credentials registered".to_string(),
+            });
+        }
+
+        Ok(WebAuthnRequestOptions {
+            challenge: self.issue_challenge("authenticate", user_id)?,
+            rp_id: self.webauthn.rp_id.clone(),
+            allow_credentials,
+            timeout_ms: WEBAUTHN_CHALLENGE_TTL.as_millis() as u64,
+        })
+    }
+
+    /// Verify a WebAuthn assertion and mark the session verified
+    pub async fn finish_webauthn_authentication(
+        &self,
+        user_id: Uuid,
+        assertion: WebAuthnAssertion,
+        session_id: Option<&str>,
+    ) -> Result<bool, HimsError> {
+        let challenge = self.take_challenge("authenticate", user_id)?;
+        let factors = self.get_mfa_factors(user_id).await?;
+        let Some(factor) = factors
+            .iter()
+            .find(|f| f.factor_type == MfaFactorType::WebAuthn && f.credential_id.as_deref() == Some(assertion.credential_id.as_str()))
+        else {
+            return Ok(false);
+        };
+        let Some(public_key) = factor.public_key.as_deref() else {
+            return Ok(false);
+        };
+
+        let client_data = auth_mfa::decode_base64url(&assertion.client_data_json)?;
+        let authenticator_data = auth_mfa::decode_base64url(&assertion.authenticator_data)?;
+        let signature = auth_mfa::decode_base64url(&assertion.signature)?;
+
+        auth_mfa::verify_client_data(&client_data, "webauthn.get", &challenge, &self.webauthn)?;
+        let data = auth_mfa::verify_authenticator_data(&authenticator_data, &self.webauthn)?;
+        auth_mfa::verify_assertion_signature(public_key, &authenticator_data, &client_data, &signature)?;
+
+        // A counter that does not increase indicates a cloned authenticator
+        let used = sqlx::query(USE_WEBAUTHN_CREDENTIAL)
+            .bind(factor.id)
+            .bind(data.sign_count as i64)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        if used.rows_affected() == 0 {
+            tracing::warn!("WebAuthn sign count regression for credential {}", assertion.credential_id);
+            return Ok(false);
+        }
+
+        self.mark_mfa_verified(user_id, session_id).await?;
+        Ok(true)
+    }
+
+    /// Active MFA factors for a user
+    pub async fn get_mfa_factors(&self, user_id: Uuid) -> Result<Vec<MfaFactor>, HimsError> {
+        let rows = sqlx::query(GET_MFA_FACTORS)
+            .bind(user_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| MfaFactor {
+                id: row.get("id"),
+                factor_type: if row.get::<String, _>("factor_type") == "webauthn" {
+                    MfaFactorType::WebAuthn
+                } else {
+                    MfaFactorType::Totp
+                },
+                name: row.get("name"),
+                credential_id: row.get("credential_id"),
+                confirmed_at: row.get("confirmed_at"),
+                last_used_at: row.get("last_used_at"),
+                created_at: row.get("created_at"),
+                totp_secret: row.get("totp_secret"),
+                last_used_step: row.get("last_used_step"),
+                public_key: row.get("public_key"),
+                sign_count: row.get("sign_count"),
+            })
+            .collect())
+    }
+
+    /// Remove an MFA factor
+    pub async fn remove_mfa_factor(&self, user_id: Uuid, factor_id: Uuid) -> Result<bool, HimsError> {
+        let result = sqlx::query(DEACTIVATE_MFA_FACTOR)
+            .bind(factor_id)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Persist MFA verification into the session context used by the authorization engine
+    async fn mark_mfa_verified(&self, user_id: Uuid, session_id: Option<&str>) -> Result<(), HimsError> {
+        let Some(session_id) = session_id else {
+            tracing::warn!("MFA verified for user {} without a session; step-up will not persist", user_id);
+            return Ok(());
+        };
+
+        sqlx::query(MARK_SESSION_MFA_VERIFIED)
+            .bind(session_id)
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("MFA verified for user {} in session {}", user_id, session_id);
+        Ok(())
+    }
+
+    fn match_totp(factor: &MfaFactor, code: &str) -> Option<u64> {
+        let secret = auth_mfa::base32_decode(factor.totp_secret.as_deref()?)?;
+        auth_mfa::verify_totp(
+            &secret,
+            code,
+            Utc::now().timestamp() as u64,
+            TOTP_WINDOW,
+            factor.last_used_step.map(|step| step as u64),
+        )
+    }
+
+    fn issue_challenge(&self, purpose: &str, user_id: Uuid) -> Result<String, HimsError> {
+        let mut bytes = [0u8; 32];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| HimsError::InternalError { message: "Failed to generate WebAuthn challenge".to_string() })?;
+        let challenge = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+
+        if let Ok(mut challenges) = self.challenges.write() {
+            challenges.retain(|_, pending| pending.created_at.elapsed() < WEBAUTHN_CHALLENGE_TTL);
+            challenges.insert(format!("{}:{}", purpose, user_id), PendingChallenge {
+                challenge: challenge.clone(),
+                created_at: Instant::now(),
+            });
+        }
+        Ok(challenge)
+    }
+
+    fn take_challenge(&self, purpose: &str, user_id: Uuid) -> Result<String, HimsError> {
+        self.challenges
+            .write()
+            .ok()
+            .and_then(|mut challenges| challenges.remove(&format!("{}:{}", purpose, user_id)))
+            .filter(|pending| pending.created_at.elapsed() < WEBAUTHN_CHALLENGE_TTL)
+            .map(|pending| pending.challenge)
+            .ok_or_else(|| HimsError::AuthenticationError {
+                message: "WebAuthn challenge expired or not found".to_string(),
+            })
     }
 
     /// Check if user has specific permission
@@ -85,4 +994,75 @@ pub struct AuthenticatedUser {
     pub username: String,
     pub role: String,
     pub permissions: Vec<String>,
-}
\ No newline at end of file
+}
+
+/// Enrolled MFA factor. Secrets and key material are never serialized.
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaFactor {
+    pub id: Uuid,
+    pub factor_type: MfaFactorType,
+    pub name: Option<String>,
+    pub credential_id: Option<String>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    totp_secret: Option<String>,
+    #[serde(skip)]
+    last_used_step: Option<i64>,
+    #[serde(skip)]
+    public_key: Option<Vec<u8>>,
+    #[serde(skip)]
+    sign_count: i64,
+}
+
+/// Result of starting TOTP enrollment
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpEnrollment {
+    pub factor_id: Uuid,
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Options for `navigator.credentials.create()`
+#[derive(Debug, Clone, Serialize)]
+pub struct WebAuthnCreationOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub rp_name: String,
+    pub user_id: String,
+    pub user_name: String,
+    pub algorithms: Vec<i64>,
+    pub timeout_ms: u64,
+}
+
+/// Options for `navigator.credentials.get()`
+#[derive(Debug, Clone, Serialize)]
+pub struct WebAuthnRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub allow_credentials: Vec<String>,
+    pub timeout_ms: u64,
+}
+
+/// Browser registration response; binary fields are base64url
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebAuthnRegistration {
+    pub credential_id: String,
+    pub client_data_json: String,
+    /// `AuthenticatorAttestationResponse.getAuthenticatorData()`
+    pub authenticator_data: String,
+    /// `AuthenticatorAttestationResponse.getPublicKey()` (SubjectPublicKeyInfo)
+    pub public_key: String,
+    pub public_key_algorithm: i64,
+    pub name: Option<String>,
+}
+
+/// Browser assertion response; binary fields are base64url
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebAuthnAssertion {
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
@@ -0,0 +1,278 @@
+//! Auth SQL Queries
+//! 
+//! This file contains all SQL queries used by the auth service
+//! for clean separation of concerns and better maintainability.
+
+/// Store a newly issued refresh token
+pub const INSERT_REFRESH_TOKEN: &str = r#"
+    INSERT INTO refresh_tokens (
+        id, family_id, user_id, token_hash, user_claims, expires_at, created_at
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7
+    )
+"#;
+
+/// Look up a refresh token by hash
+pub const GET_REFRESH_TOKEN: &str = r#"
+    SELECT id, family_id, user_id, user_claims, expires_at, rotated_at, revoked_at
+    FROM refresh_tokens
+    WHERE token_hash = $1
+"#;
+
+/// Mark a refresh token as rotated, only if it has not been used already
+pub const ROTATE_REFRESH_TOKEN: &str = r#"
+    UPDATE refresh_tokens
+    SET rotated_at = $2
+    WHERE id = $1 AND rotated_at IS NULL AND revoked_at IS NULL
+"#;
+
+/// Revoke every refresh token in a family
+pub const REVOKE_REFRESH_TOKEN_FAMILY: &str = r#"
+    UPDATE refresh_tokens
+    SET revoked_at = $2, revocation_reason = $3
+    WHERE family_id = $1 AND revoked_at IS NULL
+"#;
+
+/// Revoke every refresh token held by a user
+pub const REVOKE_USER_REFRESH_TOKENS: &str = r#"
+    UPDATE refresh_tokens
+    SET revoked_at = $2, revocation_reason = $3
+    WHERE user_id = $1 AND revoked_at IS NULL
+"#;
+
+/// Add an access token to the revocation list
+pub const INSERT_REVOKED_ACCESS_TOKEN: &str = r#"
+    INSERT INTO revoked_access_tokens (jti, user_id, expires_at, revoked_at, reason)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (jti) DO NOTHING
+"#;
+
+/// Check whether an access token has been revoked
+pub const IS_ACCESS_TOKEN_REVOKED: &str = r#"
+    SELECT EXISTS(SELECT 1 FROM revoked_access_tokens WHERE jti = $1) AS revoked
+"#;
+
+/// Load unexpired revocations into memory
+pub const GET_ACTIVE_REVOCATIONS: &str = r#"
+    SELECT jti, expires_at FROM revoked_access_tokens WHERE expires_at > NOW()
+"#;
+
+/// Remove refresh tokens and revocations that can no longer be used
+pub const DELETE_EXPIRED_TOKENS: &str = r#"
+    WITH expired_refresh AS (
+        DELETE FROM refresh_tokens WHERE expires_at < NOW() - INTERVAL '1 day' RETURNING 1
+    ), expired_revocations AS (
+        DELETE FROM revoked_access_tokens WHERE expires_at < NOW() RETURNING 1
+    )
+    SELECT (SELECT COUNT(*) FROM expired_refresh) + (SELECT COUNT(*) FROM expired_revocations) AS deleted
+"#;
+
+/// Find the HIMS user linked to an IdP subject
+pub const GET_SSO_IDENTITY: &str = r#"
+    SELECT i.user_id, u.username, u.role, u.active
+    FROM sso_identities i
+    JOIN users u ON u.id = i.user_id
+    WHERE i.provider = $1 AND i.subject = $2
+"#;
+
+/// Record a successful SSO login
+pub const TOUCH_SSO_IDENTITY: &str = r#"
+    UPDATE sso_identities
+    SET last_login_at = $3, claims = $4
+    WHERE provider = $1 AND subject = $2
+"#;
+
+/// Provision a user on first SSO login
+pub const INSERT_SSO_USER: &str = r#"
+    INSERT INTO users (id, username, email, password_hash, role, active, name, created_at, updated_at)
+    VALUES ($1, $2, $3, '!sso', $4, true, $5, $6, $6)
+"#;
+
+/// Link an IdP subject to a HIMS user
+pub const INSERT_SSO_IDENTITY: &str = r#"
+    INSERT INTO sso_identities (id, provider, subject, user_id, email, claims, created_at, last_login_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+"#;
+
+/// Assign a role by name as part of SSO provisioning
+pub const ASSIGN_ROLE_BY_NAME: &str = r#"
+    INSERT INTO role_members (id, role_id, user_id, assigned_by, assigned_at, is_active)
+    SELECT $1, r.id, $2, NULL, $3, true
+    FROM roles r
+    WHERE r.name = $4 AND r.is_active = true
+    ON CONFLICT (role_id, user_id) WHERE is_active = true DO NOTHING
+"#;
+
+/// Register an MFA factor; TOTP factors start unconfirmed
+pub const INSERT_MFA_FACTOR: &str = r#"
+    INSERT INTO mfa_factors (id, user_id, factor_type, name, totp_secret, credential_id, public_key, sign_count, confirmed_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+"#;
+
+/// Active factors for a user
+pub const GET_MFA_FACTORS: &str = r#"
+    SELECT id, factor_type, name, totp_secret, last_used_step, credential_id, public_key,
+           sign_count, confirmed_at, last_used_at, created_at
+    FROM mfa_factors
+    WHERE user_id = $1 AND is_active = true
+    ORDER BY created_at
+"#;
+
+/// Mark a TOTP factor confirmed after its first valid code
+pub const CONFIRM_MFA_FACTOR: &str = r#"
+    UPDATE mfa_factors
+    SET confirmed_at = $3, last_used_step = $4, last_used_at = $3
+    WHERE id = $1 AND user_id = $2 AND is_active = true AND confirmed_at IS NULL
+"#;
+
+/// Consume a TOTP step; fails if the step (or a later one) was already used
+pub const USE_TOTP_STEP: &str = r#"
+    UPDATE mfa_factors
+    SET last_used_step = $2, last_used_at = $3
+    WHERE id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+"#;
+
+/// Advance a WebAuthn signature counter; fails if the counter did not increase
+pub const USE_WEBAUTHN_CREDENTIAL: &str = r#"
+    UPDATE mfa_factors
+    SET sign_count = $2, last_used_at = $3
+    WHERE id = $1 AND (sign_count < $2 OR ($2 = 0 AND sign_count = 0))
+"#;
+
+/// Remove an MFA factor
+pub const DEACTIVATE_MFA_FACTOR: &str = r#"
+    UPDATE mfa_factors
+    SET is_active = false
+    WHERE id = $1 AND user_id = $2 AND is_active = true
+"#;
+
+/// Record a login attempt with its risk assessment
+pub const INSERT_LOGIN_EVENT: &str = r#"
+    INSERT INTO login_events (id, user_id, username, session_id, success, ip_address, user_agent,
+                              device_hash, country, latitude, longitude, risk_score, risk_signals, occurred_at)
+    VALUES ($1, $2, $3, $4, $5, $6::inet, $7, $8, $9, $10, $11, $12, $13, $14)
+"#;
+
+/// Most recent successful login for a user
+pub const GET_LAST_SUCCESSFUL_LOGIN: &str = r#"
+    SELECT ip_address::text AS ip_address, device_hash, country, latitude, longitude, occurred_at
+    FROM login_events
+    WHERE user_id = $1 AND success = true
+    ORDER BY occurred_at DESC
+    LIMIT 1
+"#;
+
+/// Whether a user has logged in successfully from a device before
+pub const IS_KNOWN_DEVICE: &str = r#"
+    SELECT EXISTS (
+        SELECT 1 FROM login_events
+        WHERE user_id = $1 AND device_hash = $2 AND success = true
+    ) AS known
+"#;
+
+/// Failed login attempts for a username since a point in time
+pub const COUNT_FAILED_LOGINS: &str = r#"
+    SELECT COUNT(*) AS failures
+    FROM login_events
+    WHERE username = $1 AND success = false AND occurred_at > $2
+"#;
+
+/// Credentials and lockout state for password login
+pub const GET_USER_CREDENTIALS: &str = r#"
+    SELECT id, username, password_hash, role, active, failed_login_attempts, lockout_count,
+           locked_until, password_changed_at, must_reset_password
+    FROM users
+    WHERE username = $1
+"#;
+
+/// Count a failed password attempt, locking the account once the threshold is reached
+pub const RECORD_FAILED_PASSWORD: &str = r#"
+    UPDATE users
+    SET failed_login_attempts = CASE WHEN failed_login_attempts + 1 >= $2 THEN 0 ELSE failed_login_attempts + 1 END,
+        lockout_count = CASE WHEN failed_login_attempts + 1 >= $2 THEN lockout_count + 1 ELSE lockout_count END,
+        locked_until = CASE WHEN failed_login_attempts + 1 >= $2 THEN $3 ELSE locked_until END
+    WHERE id = $1
+    RETURNING failed_login_attempts, locked_until
+"#;
+
+/// Reset lockout state after a successful login
+pub const RECORD_SUCCESSFUL_LOGIN: &str = r#"
+    UPDATE users
+    SET failed_login_attempts = 0, lockout_count = 0, locked_until = NULL, last_login = $2
+    WHERE id = $1
+"#;
+
+/// Clear a lockout (administrator unlock)
+pub const UNLOCK_USER: &str = r#"
+    UPDATE users
+    SET failed_login_attempts = 0, lockout_count = 0, locked_until = NULL, updated_at = $2
+    WHERE id = $1
+"#;
+
+/// Flag a user to change their password at next login
+pub const SET_MUST_RESET_PASSWORD: &str = r#"
+    UPDATE users
+    SET must_reset_password = true, updated_at = $2
+    WHERE id = $1
+"#;
+
+/// Store a new password hash and clear the reset flag
+pub const UPDATE_PASSWORD: &str = r#"
+    UPDATE users
+    SET password_hash = $2, password_changed_at = $3, must_reset_password = false, updated_at = $3
+    WHERE id = $1
+"#;
+
+/// Recent password hashes for reuse checks
+pub const GET_PASSWORD_HISTORY: &str = r#"
+    SELECT password_hash
+    FROM password_history
+    WHERE user_id = $1
+    ORDER BY created_at DESC
+    LIMIT $2
+"#;
+
+/// Remember a password hash
+pub const INSERT_PASSWORD_HISTORY: &str = r#"
+    INSERT INTO password_history (id, user_id, password_hash, created_at)
+    VALUES ($1, $2, $3, $4)
+"#;
+
+/// Enroll a smart card or fingerprint for workstation login
+pub const INSERT_WORKSTATION_CREDENTIAL: &str = r#"
+    INSERT INTO workstation_credentials (id, user_id, kind, credential_id, public_key, name, created_by, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING id, user_id, kind, credential_id, public_key, name, created_by, created_at, last_used_at
+"#;
+
+/// Look up an enrolled credential and the user it logs in
+pub const GET_WORKSTATION_CREDENTIAL: &str = r#"
+    SELECT c.id, c.user_id, c.kind, c.credential_id, c.public_key, c.name, c.created_by, c.created_at,
+           c.last_used_at, u.username, u.role, u.active, u.locked_until
+    FROM workstation_credentials c
+    JOIN users u ON u.id = c.user_id
+    WHERE c.kind = $1 AND c.credential_id = $2 AND c.revoked_at IS NULL
+"#;
+
+/// Record a workstation login with a credential
+pub const TOUCH_WORKSTATION_CREDENTIAL: &str = r#"
+    UPDATE workstation_credentials
+    SET last_used_at = $2
+    WHERE id = $1
+"#;
+
+/// A user's enrolled workstation credentials
+pub const LIST_USER_WORKSTATION_CREDENTIALS: &str = r#"
+    SELECT id, user_id, kind, credential_id, public_key, name, created_by, created_at, last_used_at
+    FROM workstation_credentials
+    WHERE user_id = $1 AND revoked_at IS NULL
+    ORDER BY created_at
+"#;
+
+/// Revoke a workstation credential
+pub const REVOKE_WORKSTATION_CREDENTIAL: &str = r#"
+    UPDATE workstation_credentials
+    SET revoked_at = $2, revoked_by = $3
+    WHERE id = $1 AND revoked_at IS NULL
+    RETURNING user_id
+"#;
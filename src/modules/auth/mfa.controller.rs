@@ -0,0 +1,258 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_controller::ErrorResponse;
+use crate::modules::auth::auth_service::{
+    MfaFactor, TotpEnrollment, WebAuthnAssertion, WebAuthnCreationOptions, WebAuthnRegistration,
+    WebAuthnRequestOptions,
+};
+use crate::modules::auth::AuthService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::{extract_session_id, extract_user_from_headers};
+
+/// Multi-factor enrollment and verification controller
+pub struct MfaController {
+    auth_service: Arc<AuthService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpEnrollRequest {
+    pub account_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpConfirmRequest {
+    pub factor_id: Uuid,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpVerifyRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebAuthnRegisterBeginRequest {
+    pub user_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaVerificationResponse {
+    pub verified: bool,
+}
+
+impl MfaController {
+    /// Create new controller with injected service
+    pub fn new(auth_service: Arc<AuthService>) -> Self {
+        Self { auth_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/mfa/factors", Self::list_factors, "List enrolled factors for the current user")
+            .delete("/mfa/factors/:id", Self::remove_factor, "Remove an enrolled factor")
+            .post("/mfa/totp/enroll", Self::enroll_totp, "Start TOTP enrollment")
+            .post("/mfa/totp/confirm", Self::confirm_totp, "Confirm TOTP enrollment with a first code")
+            .post("/mfa/totp/verify", Self::verify_totp, "Verify a TOTP code for the current session")
+            .post("/mfa/webauthn/register/begin", Self::begin_registration, "Start WebAuthn credential registration")
+            .post("/mfa/webauthn/register/finish", Self::finish_registration, "Complete WebAuthn credential registration")
+            .post("/mfa/webauthn/authenticate/begin", Self::begin_authentication, "Start WebAuthn authentication")
+            .post("/mfa/webauthn/authenticate/finish", Self::finish_authentication, "Verify a WebAuthn assertion for the current session")
+            .with_state(self.auth_service.clone())
+    }
+
+    /// List enrolled factors for the current user
+    pub async fn list_factors(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<MfaFactor>>, (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+
+        match auth_service.get_mfa_factors(user_id).await {
+            Ok(factors) => Ok(Json(factors)),
+            Err(e) => Err(Self::error_response("Failed to list MFA factors", e)),
+        }
+    }
+
+    /// Remove an enrolled factor
+    pub async fn remove_factor(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+
+        match auth_service.remove_mfa_factor(user_id, id).await {
+            Ok(true) => {
+                tracing::info!("MFA factor {} removed for user {}", id, user_id);
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Factor not found".to_string(),
+                    message: format!("MFA factor with id {} not found", id),
+                }),
+            )),
+            Err(e) => Err(Self::error_response("Failed to remove MFA factor", e)),
+        }
+    }
+
+    /// Start TOTP enrollment
+    pub async fn enroll_totp(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Json(payload): Json<TotpEnrollRequest>,
+    ) -> Result<(StatusCode, Json<TotpEnrollment>), (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+        let account_name = payload.account_name.unwrap_or_else(|| user_id.to_string());
+
+        match auth_service.enroll_totp(user_id, &account_name).await {
+            Ok(enrollment) => Ok((StatusCode::CREATED, Json(enrollment))),
+            Err(e) => Err(Self::error_response("Failed to enroll TOTP", e)),
+        }
+    }
+
+    /// Confirm TOTP enrollment with a first code
+    pub async fn confirm_totp(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Json(payload): Json<TotpConfirmRequest>,
+    ) -> Result<Json<MfaVerificationResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+
+        match auth_service.confirm_totp(user_id, payload.factor_id, &payload.code).await {
+            Ok(verified) => Self::verification(verified),
+            Err(e) => Err(Self::error_response("Failed to confirm TOTP", e)),
+        }
+    }
+
+    /// Verify a TOTP code for the current session
+    pub async fn verify_totp(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Json(payload): Json<TotpVerifyRequest>,
+    ) -> Result<Json<MfaVerificationResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+        let session_id = extract_session_id(&headers);
+
+        match auth_service.verify_totp(user_id, &payload.code, session_id.as_deref()).await {
+            Ok(verified) => Self::verification(verified),
+            Err(e) => Err(Self::error_response("TOTP verification failed", e)),
+        }
+    }
+
+    /// Start WebAuthn credential registration
+    pub async fn begin_registration(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Json(payload): Json<WebAuthnRegisterBeginRequest>,
+    ) -> Result<Json<WebAuthnCreationOptions>, (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+        let user_name = payload.user_name.unwrap_or_else(|| user_id.to_string());
+
+        match auth_service.begin_webauthn_registration(user_id, &user_name) {
+            Ok(options) => Ok(Json(options)),
+            Err(e) => Err(Self::error_response("Failed to start WebAuthn registration", e)),
+        }
+    }
+
+    /// Complete WebAuthn credential registration
+    pub async fn finish_registration(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Json(payload): Json<WebAuthnRegistration>,
+    ) -> Result<(StatusCode, Json<MfaFactor>), (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+
+        match auth_service.finish_webauthn_registration(user_id, payload).await {
+            Ok(factor) => Ok((StatusCode::CREATED, Json(factor))),
+            Err(e) => Err(Self::error_response("WebAuthn registration failed", e)),
+        }
+    }
+
+    /// Start WebAuthn authentication
+    pub async fn begin_authentication(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<WebAuthnRequestOptions>, (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+
+        match auth_service.begin_webauthn_authentication(user_id).await {
+            Ok(options) => Ok(Json(options)),
+            Err(e) => Err(Self::error_response("Failed to start WebAuthn authentication", e)),
+        }
+    }
+
+    /// Verify a WebAuthn assertion for the current session
+    pub async fn finish_authentication(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Json(payload): Json<WebAuthnAssertion>,
+    ) -> Result<Json<MfaVerificationResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let user_id = Self::actor(&headers)?;
+        let session_id = extract_session_id(&headers);
+
+        match auth_service
+            .finish_webauthn_authentication(user_id, payload, session_id.as_deref())
+            .await
+        {
+            Ok(verified) => Self::verification(verified),
+            Err(e) => Err(Self::error_response("WebAuthn authentication failed", e)),
+        }
+    }
+
+    fn verification(verified: bool) -> Result<Json<MfaVerificationResponse>, (StatusCode, Json<ErrorResponse>)> {
+        if verified {
+            Ok(Json(MfaVerificationResponse { verified }))
+        } else {
+            Err((
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "MFA verification failed".to_string(),
+                    message: "The code or credential was not accepted".to_string(),
+                }),
+            ))
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::AuthenticationError { .. } => StatusCode::UNAUTHORIZED,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -1,11 +1,16 @@
 //! Auth Module
 //! 
 //! This module provides authentication and authorization functionality including:
-//! - JWT token management
-//! - User authentication
+//! - JWT token management (RS256/ES256, refresh rotation, revocation, JWKS)
+//! - User authentication with password policy, breached-password checks and lockout
+//! - TOTP and WebAuthn multi-factor authentication with step-up
+//! - OIDC single sign-on (Keycloak, Azure AD) with group-to-role mapping
+//! - Smart card (PIV) and fingerprint login on shared clinical workstations
 //! - Role-based access control (RBAC)
 //! - Healthcare provider verification
-//! - Session management
+//! - Session management (idle/absolute timeouts, log out everywhere)
+//! - Request principals (user, session, purpose of use) for audit logs
+//! - Risk-based adaptive authentication scoring
 
 #[path = "auth.controller.rs"]
 pub mod auth_controller;
@@ -13,39 +18,109 @@ pub mod auth_controller;
 pub mod auth_service;
 #[path = "auth.middleware.rs"]
 pub mod auth_middleware;
+#[path = "auth.jwt.rs"]
+pub mod auth_jwt;
+#[path = "auth.context.rs"]
+pub mod auth_context;
+#[path = "auth.sql.rs"]
+pub mod auth_sql;
+#[path = "auth.password.rs"]
+pub mod auth_password;
+#[path = "password.controller.rs"]
+pub mod password_controller;
+#[path = "auth.mfa.rs"]
+pub mod auth_mfa;
+#[path = "mfa.controller.rs"]
+pub mod mfa_controller;
+#[path = "risk.service.rs"]
+pub mod risk_service;
+#[path = "session.service.rs"]
+pub mod session_service;
+#[path = "session.controller.rs"]
+pub mod session_controller;
+#[path = "sso.service.rs"]
+pub mod sso_service;
+#[path = "sso.controller.rs"]
+pub mod sso_controller;
+#[path = "workstation.service.rs"]
+pub mod workstation_service;
+#[path = "workstation.controller.rs"]
+pub mod workstation_controller;
 
 pub use auth_controller::AuthController;
 pub use auth_service::{AuthService, AuthenticatedUser};
 pub use auth_middleware::AuthMiddleware;
+pub use auth_context::AuthContext;
+pub use auth_jwt::{JwtConfig, JwtManager, TokenPair};
+pub use auth_mfa::{MfaFactorType, WebAuthnConfig};
+pub use auth_password::{LockoutPolicy, PasswordPolicy, PasswordViolation};
+pub use password_controller::PasswordController;
+pub use mfa_controller::MfaController;
+pub use risk_service::{RiskAssessment, RiskConfig, RiskEngine, RiskSignal};
+pub use session_service::{SessionClient, SessionConfig, SessionManager, SessionRecord};
+pub use session_controller::SessionController;
+pub use sso_service::{SsoConfig, SsoService};
+pub use sso_controller::SsoController;
+pub use workstation_service::{CredentialAdapter, WorkstationConfig, WorkstationService};
+pub use workstation_controller::WorkstationController;
 
-use axum::Router;
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::utils::api_router::ApiRouter;
+
 /// Auth Module Configuration
 pub struct AuthModule {
     pub service: Arc<AuthService>,
     pub controller: Arc<AuthController>,
     pub middleware: Arc<AuthMiddleware>,
+    pub password_controller: Arc<PasswordController>,
+    pub mfa_controller: Arc<MfaController>,
+    pub session_controller: Arc<SessionController>,
+    pub sso_service: Arc<SsoService>,
+    pub sso_controller: Arc<SsoController>,
+    pub workstation_service: Arc<WorkstationService>,
+    pub workstation_controller: Arc<WorkstationController>,
 }
 
 impl AuthModule {
     /// Create a new Auth Module with dependency injection
     pub fn new(db_pool: PgPool) -> Self {
-        let service = Arc::new(AuthService::new(db_pool));
+        let service = Arc::new(AuthService::new(db_pool.clone()));
         let controller = Arc::new(AuthController::new(service.clone()));
         let middleware = Arc::new(AuthMiddleware::new(service.clone()));
+        let password_controller = Arc::new(PasswordController::new(service.clone()));
+        let mfa_controller = Arc::new(MfaController::new(service.clone()));
+        let session_controller = Arc::new(SessionController::new(service.clone()));
+        let sso_service = Arc::new(SsoService::new(db_pool.clone(), service.clone(), SsoConfig::from_env()));
+        let sso_controller = Arc::new(SsoController::new(sso_service.clone()));
+        let workstation_service =
+            Arc::new(WorkstationService::new(db_pool, service.clone(), WorkstationConfig::from_env()));
+        let workstation_controller = Arc::new(WorkstationController::new(workstation_service.clone()));
         
         Self {
             service,
             controller,
             middleware,
+            password_controller,
+            mfa_controller,
+            session_controller,
+            sso_service,
+            sso_controller,
+            workstation_service,
+            workstation_controller,
         }
     }
 
     /// Register routes for this module
-    pub fn routes(&self) -> Router {
-        self.controller.routes()
+    pub fn routes(&self) -> ApiRouter {
+        self.controller
+            .routes()
+            .merge(self.password_controller.routes())
+            .merge(self.mfa_controller.routes())
+            .merge(self.session_controller.routes())
+            .merge(self.sso_controller.routes())
+            .merge(self.workstation_controller.routes())
     }
 
     /// Get service instance for dependency injection
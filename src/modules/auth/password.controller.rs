@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_controller::ErrorResponse;
+use crate::modules::auth::auth_password::PasswordViolation;
+use crate::modules::auth::AuthService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Password change, policy check and account lockout administration
+pub struct PasswordController {
+    auth_service: Arc<AuthService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub username: String,
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckPasswordRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckPasswordResponse {
+    pub acceptable: bool,
+    pub violations: Vec<PasswordViolation>,
+}
+
+impl PasswordController {
+    /// Create new controller with injected service
+    pub fn new(auth_service: Arc<AuthService>) -> Self {
+        Self { auth_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/password/change", Self::change_password, "Change a password")
+            .post("/password/check", Self::check_password, "Check a candidate password against policy and known breaches")
+            .post("/users/:user_id/unlock", Self::unlock_user, "Clear a user's lockout (admin)")
+            .post("/users/:user_id/force-reset", Self::force_reset, "Require a user to change their password at next login (admin)")
+            .with_state(self.auth_service.clone())
+    }
+
+    /// Change a password; also completes forced and expiry resets
+    pub async fn change_password(
+        State(auth_service): State<Arc<AuthService>>,
+        Json(payload): Json<ChangePasswordRequest>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Password change request for user: {}", payload.username);
+
+        match auth_service
+            .change_password(&payload.username, &payload.current_password, &payload.new_password)
+            .await
+        {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to change password", e)),
+        }
+    }
+
+    /// Check a candidate password against policy and known breaches
+    pub async fn check_password(
+        State(auth_service): State<Arc<AuthService>>,
+        Json(payload): Json<CheckPasswordRequest>,
+    ) -> Result<Json<CheckPasswordResponse>, (StatusCode, Json<ErrorResponse>)> {
+        match auth_service.check_password(&payload.username, &payload.password).await {
+            Ok(violations) => Ok(Json(CheckPasswordResponse {
+                acceptable: violations.is_empty(),
+                violations,
+            })),
+            Err(e) => Err(Self::error_response("Failed to check password", e)),
+        }
+    }
+
+    /// Clear a user's lockout (admin)
+    pub async fn unlock_user(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Path(user_id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} unlocking user {}", actor, user_id);
+
+        match auth_service.unlock_user(actor, user_id).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to unlock user", e)),
+        }
+    }
+
+    /// Require a user to change their password at next login (admin)
+    pub async fn force_reset(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Path(user_id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} forcing password reset for user {}", actor, user_id);
+
+        match auth_service.force_password_reset(actor, user_id).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to force password reset", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::AuthenticationError { .. } => StatusCode::UNAUTHORIZED,
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,275 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_sql::*;
+use crate::modules::auth::session_service::SessionClient;
+
+/// Mean Earth radius used for great-circle distances
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Weights and thresholds for adaptive authentication scoring
+#[derive(Debug, Clone)]
+pub struct RiskConfig {
+    pub new_device_weight: f32,
+    pub geo_change_weight: f32,
+    pub impossible_travel_weight: f32,
+    /// Added per recent failed attempt, capped at `max_failed_attempts_weight`
+    pub failed_attempt_weight: f32,
+    pub max_failed_attempts_weight: f32,
+    /// How far back failed attempts count
+    pub failed_attempt_window_mins: i64,
+    /// Travel faster than this between logins is considered impossible
+    pub max_travel_speed_kmh: f64,
+    /// Scores at or above this are logged as high risk
+    pub high_risk_threshold: f32,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            new_device_weight: 0.25,
+            geo_change_weight: 0.2,
+            impossible_travel_weight: 0.6,
+            failed_attempt_weight: 0.1,
+            max_failed_attempts_weight: 0.3,
+            failed_attempt_window_mins: 30,
+            max_travel_speed_kmh: 900.0,
+            high_risk_threshold: 0.7,
+        }
+    }
+}
+
+/// Signals contributing to a risk score
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "signal", rename_all = "snake_case")]
+pub enum RiskSignal {
+    NewDevice,
+    GeoChange { from: String, to: String },
+    ImpossibleTravel { distance_km: f64, speed_kmh: f64 },
+    FailedAttempts { count: u32 },
+}
+
+/// Outcome of scoring a login
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskAssessment {
+    /// 0.0 (no risk) to 1.0 (maximum risk)
+    pub score: f32,
+    pub signals: Vec<RiskSignal>,
+}
+
+/// Where and from what device a login happened
+#[derive(Debug, Clone)]
+pub struct LoginObservation {
+    pub device_hash: Option<String>,
+    pub country: Option<String>,
+    pub coordinates: Option<(f64, f64)>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl LoginObservation {
+    pub fn from_client(client: &SessionClient, occurred_at: DateTime<Utc>) -> Self {
+        Self {
+            device_hash: client.device_hash(),
+            country: client.country.clone(),
+            coordinates: client.coordinates,
+            occurred_at,
+        }
+    }
+}
+
+/// Scores logins from device, location and failed-attempt history
+pub struct RiskEngine {
+    pool: PgPool,
+    config: RiskConfig,
+}
+
+impl RiskEngine {
+    pub fn new(pool: PgPool, config: RiskConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub fn config(&self) -> &RiskConfig {
+        &self.config
+    }
+
+    /// Score a login for a user against their login history
+    pub async fn assess_login(
+        &self,
+        user_id: Uuid,
+        username: &str,
+        client: &SessionClient,
+    ) -> Result<RiskAssessment, HimsError> {
+        let current = LoginObservation::from_client(client, Utc::now());
+
+        let previous = sqlx::query(GET_LAST_SUCCESSFUL_LOGIN)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .map(|row| LoginObservation {
+                device_hash: row.get("device_hash"),
+                country: row.get("country"),
+                coordinates: match (row.get::<Option<f64>, _>("latitude"), row.get::<Option<f64>, _>("longitude")) {
+                    (Some(lat), Some(lon)) => Some((lat, lon)),
+                    _ => None,
+                },
+                occurred_at: row.get("occurred_at"),
+            });
+
+        // Without any history there is nothing to compare the device against
+        let known_device = match (&previous, &current.device_hash) {
+            (None, _) | (_, None) => true,
+            (Some(_), Some(device_hash)) => sqlx::query(IS_KNOWN_DEVICE)
+                .bind(user_id)
+                .bind(device_hash)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+                .get("known"),
+        };
+
+        let failed_attempts = self.count_failed_attempts(username).await?;
+        let assessment = Self::evaluate(&self.config, &current, previous.as_ref(), known_device, failed_attempts);
+
+        if assessment.score >= self.config.high_risk_threshold {
+            tracing::warn!("High-risk login for user {}: score {:.2} {:?}", user_id, assessment.score, assessment.signals);
+        }
+        Ok(assessment)
+    }
+
+    /// Failed attempts for a username within the configured window
+    pub async fn count_failed_attempts(&self, username: &str) -> Result<u32, HimsError> {
+        let since = Utc::now() - Duration::minutes(self.config.failed_attempt_window_mins);
+        let failures: i64 = sqlx::query(COUNT_FAILED_LOGINS)
+            .bind(username)
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .get("failures");
+
+        Ok(failures as u32)
+    }
+
+    /// Record a login attempt. Successful logins become history for later scoring.
+    pub async fn record_login(
+        &self,
+        user_id: Option<Uuid>,
+        username: &str,
+        session_id: Option<&str>,
+        client: &SessionClient,
+        success: bool,
+        assessment: Option<&RiskAssessment>,
+    ) -> Result<(), HimsError> {
+        sqlx::query(INSERT_LOGIN_EVENT)
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .bind(username)
+            .bind(session_id)
+            .bind(success)
+            .bind(&client.ip_address)
+            .bind(&client.user_agent)
+            .bind(client.device_hash())
+            .bind(&client.country)
+            .bind(client.coordinates.map(|(lat, _)| lat))
+            .bind(client.coordinates.map(|(_, lon)| lon))
+            .bind(assessment.map_or(0.0, |a| a.score))
+            .bind(serde_json::to_value(assessment.map(|a| &a.signals)).unwrap_or_default())
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Combine risk signals into a score
+    pub fn evaluate(
+        config: &RiskConfig,
+        current: &LoginObservation,
+        previous: Option<&LoginObservation>,
+        known_device: bool,
+        failed_attempts: u32,
+    ) -> RiskAssessment {
+        let mut signals = Vec::new();
+        let mut score = 0.0f32;
+
+        if !known_device {
+            signals.push(RiskSignal::NewDevice);
+            score += config.new_device_weight;
+        }
+
+        if let Some(previous) = previous {
+            if let (Some(from), Some(to)) = (&previous.country, &current.country) {
+                if !from.eq_ignore_ascii_case(to) {
+                    signals.push(RiskSignal::GeoChange { from: from.clone(), to: to.clone() });
+                    score += config.geo_change_weight;
+                }
+            }
+
+            if let (Some(from), Some(to)) = (previous.coordinates, current.coordinates) {
+                let distance_km = haversine_km(from, to);
+                // Floor at one minute so back-to-back logins do not divide by ~zero
+                let hours = ((current.occurred_at - previous.occurred_at).num_seconds().max(60)) as f64 / 3600.0;
+                let speed_kmh = distance_km / hours;
+                if speed_kmh > config.max_travel_speed_kmh {
+                    signals.push(RiskSignal::ImpossibleTravel { distance_km, speed_kmh });
+                    score += config.impossible_travel_weight;
+                }
+            }
+        }
+
+        if failed_attempts > 0 {
+            signals.push(RiskSignal::FailedAttempts { count: failed_attempts });
+            score += (failed_attempts as f32 * config.failed_attempt_weight).min(config.max_failed_attempts_weight);
+        }
+
+        RiskAssessment {
+            score: score.min(1.0),
+            signals,
+        }
+    }
+}
+
+/// Great-circle distance between two latitude/longitude points
+pub fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(country: &str, coordinates: (f64, f64), occurred_at: DateTime<Utc>) -> LoginObservation {
+        LoginObservation {
+            device_hash: Some("device".to_string()),
+            country: Some(country.to_string()),
+            coordinates: Some(coordinates),
+            occurred_at,
+        }
+    }
+
+    #[test]
+    fn test_impossible_travel_and_new_device() {
+        let config = RiskConfig::default();
+        let now = Utc::now();
+        let mumbai = observation("IN", (19.07, 72.87), now - Duration::hours(1));
+        let london = observation("GB", (51.51, -0.13), now);
+
+        let assessment = RiskEngine::evaluate(&config, &london, Some(&mumbai), false, 0);
+        assert!(assessment.signals.contains(&RiskSignal::NewDevice));
+        assert!(assessment.signals.iter().any(|s| matches!(s, RiskSignal::ImpossibleTravel { .. })));
+        assert_eq!(assessment.score, 1.0);
+
+        let routine = observation("IN", (19.1, 72.9), now);
+        let assessment = RiskEngine::evaluate(&config, &routine, Some(&mumbai), true, 2);
+        assert_eq!(assessment.signals, vec![RiskSignal::FailedAttempts { count: 2 }]);
+        assert!((assessment.score - 0.2).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,219 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_controller::ErrorResponse;
+use crate::modules::auth::session_service::SessionRecord;
+use crate::modules::auth::AuthService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Session controller for listing and ending login sessions
+pub struct SessionController {
+    auth_service: Arc<AuthService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActiveSessionQuery {
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HighRiskSessionQuery {
+    pub threshold: Option<f32>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutResponse {
+    pub sessions_ended: u64,
+}
+
+impl SessionController {
+    /// Create new controller with injected service
+    pub fn new(auth_service: Arc<AuthService>) -> Self {
+        Self { auth_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/sessions", Self::list_own_sessions, "List the current user's active sessions")
+            .post("/sessions/logout-all", Self::logout_all, "Log the current user out of every session")
+            .delete("/sessions/:session_id", Self::end_session, "End one session")
+            .get("/sessions/admin/active", Self::list_active_sessions, "List active sessions across users (admin)")
+            .get("/sessions/admin/high-risk", Self::list_high_risk_sessions, "List high-risk sessions (admin)")
+            .post("/sessions/admin/users/:user_id/logout-all", Self::logout_user, "Log a user out of every session (admin)")
+            .with_state(self.auth_service.clone())
+    }
+
+    /// List the current user's active sessions
+    pub async fn list_own_sessions(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<SessionRecord>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match auth_service.sessions().get_user_sessions(actor).await {
+            Ok(sessions) => Ok(Json(sessions)),
+            Err(e) => Err(Self::error_response("Failed to list sessions", e)),
+        }
+    }
+
+    /// Log the current user out of every session
+    pub async fn logout_all(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<LogoutResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} logging out everywhere", actor);
+
+        match auth_service.logout_everywhere(actor, "logout_everywhere").await {
+            Ok(sessions_ended) => Ok(Json(LogoutResponse { sessions_ended })),
+            Err(e) => Err(Self::error_response("Failed to log out", e)),
+        }
+    }
+
+    /// End one session. Users may end their own sessions; admins may end any.
+    pub async fn end_session(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Path(session_id): Path<String>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        let sessions = auth_service.sessions();
+
+        let session = match sessions.get_session(&session_id).await {
+            Ok(Some(session)) => session,
+            Ok(None) => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Session not found".to_string(),
+                        message: format!("Session {} not found", session_id),
+                    }),
+                ));
+            }
+            Err(e) => return Err(Self::error_response("Failed to retrieve session", e)),
+        };
+
+        if session.user_id != actor {
+            sessions
+                .require_admin(actor)
+                .await
+                .map_err(|e| Self::error_response("Failed to end session", e))?;
+        }
+
+        match auth_service.logout_session(&session_id, "session_ended").await {
+            Ok(()) => {
+                tracing::info!("Session {} ended by {}", session_id, actor);
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Err(e) => Err(Self::error_response("Failed to end session", e)),
+        }
+    }
+
+    /// List active sessions across users (admin)
+    pub async fn list_active_sessions(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Query(params): Query<ActiveSessionQuery>,
+    ) -> Result<Json<Vec<SessionRecord>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        let sessions = auth_service.sessions();
+
+        let result = match params.user_id {
+            Some(user_id) => match sessions.require_admin(actor).await {
+                Ok(()) => sessions.get_user_sessions(user_id).await,
+                Err(e) => Err(e),
+            },
+            None => {
+                sessions
+                    .get_active_sessions(actor, params.limit.unwrap_or(100), params.offset.unwrap_or(0))
+                    .await
+            }
+        };
+
+        match result {
+            Ok(sessions) => Ok(Json(sessions)),
+            Err(e) => Err(Self::error_response("Failed to list active sessions", e)),
+        }
+    }
+
+    /// List high-risk sessions (admin)
+    pub async fn list_high_risk_sessions(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Query(params): Query<HighRiskSessionQuery>,
+    ) -> Result<Json<Vec<SessionRecord>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match auth_service
+            .sessions()
+            .get_high_risk_sessions(actor, params.threshold, params.limit.unwrap_or(100))
+            .await
+        {
+            Ok(sessions) => Ok(Json(sessions)),
+            Err(e) => Err(Self::error_response("Failed to list high-risk sessions", e)),
+        }
+    }
+
+    /// Log a user out of every session (admin)
+    pub async fn logout_user(
+        State(auth_service): State<Arc<AuthService>>,
+        headers: HeaderMap,
+        Path(user_id): Path<Uuid>,
+    ) -> Result<Json<LogoutResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        if let Err(e) = auth_service.sessions().require_admin(actor).await {
+            return Err(Self::error_response("Failed to log out user", e));
+        }
+
+        tracing::info!("User {} logging out user {} everywhere", actor, user_id);
+        match auth_service.logout_everywhere(user_id, "admin_logout").await {
+            Ok(sessions_ended) => Ok(Json(LogoutResponse { sessions_ended })),
+            Err(e) => Err(Self::error_response("Failed to log out user", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
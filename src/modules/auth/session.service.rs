@@ -0,0 +1,480 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, Extensions, HeaderMap},
+};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::authorization_sql::sessions::*;
+use crate::modules::authorization::{Action, SessionContext};
+use crate::modules::rate_limit::RateLimitClient;
+use crate::modules::role::RoleService;
+
+/// Session timeout settings
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Sessions without activity for this long are ended
+    pub idle_timeout_secs: i64,
+    /// Sessions end this long after login regardless of activity
+    pub absolute_timeout_secs: i64,
+    /// Risk score at or above which a session is listed as high risk
+    pub high_risk_threshold: f32,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        let env_secs = |name: &str, default: i64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            idle_timeout_secs: env_secs("SESSION_IDLE_TIMEOUT_SECS", 15 * 60),
+            absolute_timeout_secs: env_secs("SESSION_ABSOLUTE_TIMEOUT_SECS", 12 * 60 * 60),
+            high_risk_threshold: 0.7,
+        }
+    }
+}
+
+/// Client details recorded when a session starts
+#[derive(Debug, Clone, Default)]
+pub struct SessionClient {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub department_id: Option<Uuid>,
+    pub location_id: Option<Uuid>,
+    /// Stable device identifier supplied by native clients
+    pub device_id: Option<String>,
+    /// ISO country code resolved by a trusted edge proxy
+    pub country: Option<String>,
+    /// Latitude/longitude resolved by a trusted edge proxy
+    pub coordinates: Option<(f64, f64)>,
+}
+
+impl SessionClient {
+    /// Client of a request, at the address the rate limiter counted it
+    /// against: `X-Forwarded-For` is only believed from a trusted proxy,
+    /// and so are the edge's geolocation headers. Outside the rate limiter,
+    /// the connection's address and no location.
+    pub fn from_request(extensions: &Extensions, headers: &HeaderMap) -> Self {
+        let client = extensions.get::<RateLimitClient>().cloned().unwrap_or_else(|| RateLimitClient {
+            ip_address: extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_string()),
+            ..RateLimitClient::default()
+        });
+        let mut session_client = Self {
+            ip_address: client.ip_address,
+            ..Self::from_headers(headers)
+        };
+        if client.via_trusted_proxy {
+            session_client.locate(headers);
+        }
+        session_client
+    }
+
+    /// Details the client states about itself. Its address and location
+    /// are left out, as headers alone cannot vouch for them.
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header_uuid = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| Uuid::parse_str(s).ok())
+        };
+
+        Self {
+            user_agent: Self::header(headers, "user-agent"),
+            department_id: header_uuid("x-department-id"),
+            location_id: header_uuid("x-location-id"),
+            device_id: Self::header(headers, "x-device-id"),
+            ..Self::default()
+        }
+    }
+
+    /// Geolocation headers as added by CDNs / load balancers (Cloudflare naming)
+    fn locate(&mut self, headers: &HeaderMap) {
+        let header_f64 = |name: &str| Self::header(headers, name).and_then(|s| s.parse::<f64>().ok());
+        self.coordinates = match (header_f64("cf-iplatitude"), header_f64("cf-iplongitude")) {
+            (Some(lat), Some(lon)) => Some((lat, lon)),
+            _ => None,
+        };
+        self.country = Self::header(headers, "cf-ipcountry").filter(|c| c.len() == 2 && c != "XX");
+    }
+
+    fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+        headers.get(name).and_then(|h| h.to_str().ok()).map(|s| s.to_string())
+    }
+
+    /// Fingerprint identifying the client device across logins
+    pub fn device_hash(&self) -> Option<String> {
+        let source = self.device_id.as_deref().or(self.user_agent.as_deref())?;
+        let digest = Sha256::digest(source.as_bytes());
+        Some(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SessionClient {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_request(&parts.extensions, &parts.headers))
+    }
+}
+
+/// Persisted session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub user_id: Uuid,
+    pub department_id: Option<Uuid>,
+    pub location_id: Option<Uuid>,
+    pub shift_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub mfa_verified: bool,
+    pub mfa_verified_at: Option<DateTime<Utc>>,
+    pub risk_score: f32,
+}
+
+impl SessionRecord {
+    fn from_row(row: &PgRow) -> Self {
+        Self {
+            session_id: row.get("session_id"),
+            user_id: row.get("user_id"),
+            department_id: row.get("department_id"),
+            location_id: row.get("location_id"),
+            shift_id: row.get("shift_id"),
+            ip_address: row.get("ip_address"),
+            user_agent: row.get("user_agent"),
+            created_at: row.get::<Option<DateTime<Utc>>, _>("created_at").unwrap_or_else(Utc::now),
+            last_activity: row.get::<Option<DateTime<Utc>>, _>("last_activity").unwrap_or_else(Utc::now),
+            expires_at: row.get("expires_at"),
+            mfa_verified: row.get::<Option<bool>, _>("mfa_verified").unwrap_or(false),
+            mfa_verified_at: row.get("mfa_verified_at"),
+            risk_score: row.get::<Option<f32>, _>("risk_score").unwrap_or(0.0),
+        }
+    }
+
+    /// Authorization engine view of the session
+    pub fn to_session_context(&self) -> SessionContext {
+        SessionContext {
+            user_id: self.user_id,
+            session_id: self.session_id.clone(),
+            ip_address: self.ip_address.clone(),
+            user_agent: self.user_agent.clone(),
+            department_id: self.department_id,
+            location_id: self.location_id,
+            shift_id: self.shift_id,
+            mfa_verified: self.mfa_verified,
+            mfa_verified_at: self.mfa_verified_at,
+            risk_score: self.risk_score,
+        }
+    }
+}
+
+/// Session lifecycle backed by `authorization_session_context`
+pub struct SessionManager {
+    pool: PgPool,
+    config: SessionConfig,
+    role_service: RoleService,
+}
+
+impl SessionManager {
+    pub fn new(pool: PgPool, config: SessionConfig) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Record a new session at login. The session ends at the absolute timeout.
+    pub async fn start_session(
+        &self,
+        user_id: Uuid,
+        session_id: &str,
+        client: &SessionClient,
+        risk_score: f32,
+    ) -> Result<SessionRecord, HimsError> {
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(self.config.absolute_timeout_secs);
+
+        sqlx::query(UPSERT_SESSION_CONTEXT)
+            .bind(session_id)
+            .bind(user_id)
+            .bind(client.department_id)
+            .bind(client.location_id)
+            .bind(None::<Uuid>)
+            .bind(&client.ip_address)
+            .bind(&client.user_agent)
+            .bind(expires_at)
+            .bind(false)
+            .bind(risk_score)
+            .bind(serde_json::json!({}))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Session {} started for user {}", session_id, user_id);
+        Ok(SessionRecord {
+            session_id: session_id.to_string(),
+            user_id,
+            department_id: client.department_id,
+            location_id: client.location_id,
+            shift_id: None,
+            ip_address: client.ip_address.clone(),
+            user_agent: client.user_agent.clone(),
+            created_at: now,
+            last_activity: now,
+            expires_at,
+            mfa_verified: false,
+            mfa_verified_at: None,
+            risk_score,
+        })
+    }
+
+    /// Load an active session without recording activity
+    pub async fn get_session(&self, session_id: &str) -> Result<Option<SessionRecord>, HimsError> {
+        let row = sqlx::query(GET_SESSION_CONTEXT)
+            .bind(session_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.as_ref().map(SessionRecord::from_row))
+    }
+
+    /// Record activity on a session. Returns `None` and ends the session when
+    /// it is unknown, idle for too long or past its absolute lifetime.
+    pub async fn touch(&self, session_id: &str) -> Result<Option<SessionRecord>, HimsError> {
+        let Some(session) = self.get_session(session_id).await? else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        if let Some(reason) = Self::timeout_reason(&self.config, &session, now) {
+            tracing::info!("Session {} ended: {}", session_id, reason);
+            self.end_session(session_id).await?;
+            return Ok(None);
+        }
+
+        sqlx::query(UPDATE_SESSION_ACTIVITY)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(Some(SessionRecord {
+            last_activity: now,
+            ..session
+        }))
+    }
+
+    /// End a single session
+    pub async fn end_session(&self, session_id: &str) -> Result<(), HimsError> {
+        sqlx::query(INVALIDATE_SESSION)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// End every session held by a user
+    pub async fn end_all_sessions(&self, user_id: Uuid) -> Result<u64, HimsError> {
+        let result = sqlx::query(INVALIDATE_USER_SESSIONS)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Ended {} sessions for user {}", result.rows_affected(), user_id);
+        Ok(result.rows_affected())
+    }
+
+    /// Active sessions for a user
+    pub async fn get_user_sessions(&self, user_id: Uuid) -> Result<Vec<SessionRecord>, HimsError> {
+        let rows = sqlx::query(GET_USER_ACTIVE_SESSIONS)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(SessionRecord::from_row).collect())
+    }
+
+    /// All active sessions (admin)
+    pub async fn get_active_sessions(
+        &self,
+        actor: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SessionRecord>, HimsError> {
+        self.require_admin(actor).await?;
+
+        let rows = sqlx::query(GET_ACTIVE_SESSIONS)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(SessionRecord::from_row).collect())
+    }
+
+    /// Active sessions at or above the risk threshold (admin)
+    pub async fn get_high_risk_sessions(
+        &self,
+        actor: Uuid,
+        threshold: Option<f32>,
+        limit: i64,
+    ) -> Result<Vec<SessionRecord>, HimsError> {
+        self.require_admin(actor).await?;
+
+        let rows = sqlx::query(GET_HIGH_RISK_SESSIONS)
+            .bind(threshold.unwrap_or(self.config.high_risk_threshold))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(SessionRecord::from_row).collect())
+    }
+
+    /// Deactivate expired and idle sessions
+    pub async fn cleanup_expired_sessions(&self) -> Result<u64, HimsError> {
+        let expired = sqlx::query(CLEANUP_EXPIRED_SESSIONS)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let idle = sqlx::query(CLEANUP_IDLE_SESSIONS)
+            .bind(Utc::now() - Duration::seconds(self.config.idle_timeout_secs))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(expired.rows_affected() + idle.rows_affected())
+    }
+
+    /// Whether the actor may manage other users' sessions
+    pub async fn require_admin(&self, actor: Uuid) -> Result<(), HimsError> {
+        if !self.role_service.get_user_permissions(actor).await?.contains(&Action::ManageUsers) {
+            tracing::warn!("User {} lacks {} permission", actor, Action::ManageUsers);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", Action::ManageUsers),
+            });
+        }
+        Ok(())
+    }
+
+    /// Why a session should end at `now`, if it should
+    pub fn timeout_reason(config: &SessionConfig, session: &SessionRecord, now: DateTime<Utc>) -> Option<&'static str> {
+        if now >= session.expires_at
+            || now - session.created_at > Duration::seconds(config.absolute_timeout_secs)
+        {
+            Some("absolute timeout")
+        } else if now - session.last_activity > Duration::seconds(config.idle_timeout_secs) {
+            Some("idle timeout")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(created_mins_ago: i64, idle_mins: i64) -> SessionRecord {
+        let now = Utc::now();
+        SessionRecord {
+            session_id: "s".to_string(),
+            user_id: Uuid::new_v4(),
+            department_id: None,
+            location_id: None,
+            shift_id: None,
+            ip_address: None,
+            user_agent: None,
+            created_at: now - Duration::minutes(created_mins_ago),
+            last_activity: now - Duration::minutes(idle_mins),
+            expires_at: now + Duration::hours(1),
+            mfa_verified: false,
+            mfa_verified_at: None,
+            risk_score: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_timeout_reason() {
+        let config = SessionConfig {
+            idle_timeout_secs: 15 * 60,
+            absolute_timeout_secs: 60 * 60,
+            high_risk_threshold: 0.7,
+        };
+        let reason = |s: &SessionRecord| SessionManager::timeout_reason(&config, s, Utc::now());
+
+        assert_eq!(reason(&session(10, 1)), None);
+        assert_eq!(reason(&session(30, 20)), Some("idle timeout"));
+        assert_eq!(reason(&session(90, 1)), Some("absolute timeout"));
+    }
+
+    #[test]
+    fn test_client_address_is_not_taken_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.9".parse().unwrap());
+        headers.insert("x-real-ip", "203.0.113.9".parse().unwrap());
+
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 50], 443))));
+        assert_eq!(SessionClient::from_request(&extensions, &headers).ip_address.as_deref(), Some("192.0.2.50"));
+
+        // The rate limiter's client, which believes trusted proxies only
+        extensions.insert(RateLimitClient {
+            ip_address: Some("198.51.100.4".to_string()),
+            ..RateLimitClient::default()
+        });
+        assert_eq!(SessionClient::from_request(&extensions, &headers).ip_address.as_deref(), Some("198.51.100.4"));
+    }
+
+    #[test]
+    fn test_location_only_from_trusted_proxies() {
+        let mut headers = HeaderMap::new();
+        headers.insert("cf-ipcountry", "NZ".parse().unwrap());
+        headers.insert("cf-iplatitude", "-41.29".parse().unwrap());
+        headers.insert("cf-iplongitude", "174.78".parse().unwrap());
+
+        let mut extensions = Extensions::new();
+        extensions.insert(RateLimitClient {
+            ip_address: Some("198.51.100.4".to_string()),
+            ..RateLimitClient::default()
+        });
+        let client = SessionClient::from_request(&extensions, &headers);
+        assert_eq!((client.country, client.coordinates), (None, None));
+
+        extensions.insert(RateLimitClient {
+            ip_address: Some("198.51.100.4".to_string()),
+            via_trusted_proxy: true,
+            ..RateLimitClient::default()
+        });
+        let client = SessionClient::from_request(&extensions, &headers);
+        assert_eq!(client.country.as_deref(), Some("NZ"));
+        assert_eq!(client.coordinates, Some((-41.29, 174.78)));
+    }
+}
@@ -0,0 +1,124 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Json, Redirect},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_controller::{ErrorResponse, LoginResponse};
+use crate::modules::auth::session_service::SessionClient;
+use crate::modules::auth::SsoService;
+use crate::utils::api_router::ApiRouter;
+
+/// Single sign-on controller for OIDC authorization-code login
+pub struct SsoController {
+    sso_service: Arc<SsoService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SsoCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+impl SsoController {
+    /// Create new controller with injected service
+    pub fn new(sso_service: Arc<SsoService>) -> Self {
+        Self { sso_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/sso/providers", Self::list_providers, "List configured SSO providers")
+            .get("/sso/:provider/login", Self::login, "Redirect the browser to the identity provider")
+            .get("/sso/:provider/callback", Self::callback, "Handle the identity provider redirect")
+            .with_state(self.sso_service.clone())
+    }
+
+    /// List configured SSO providers
+    pub async fn list_providers(
+        State(sso_service): State<Arc<SsoService>>,
+    ) -> Json<Vec<String>> {
+        Json(sso_service.providers())
+    }
+
+    /// Redirect the browser to the identity provider
+    pub async fn login(
+        State(sso_service): State<Arc<SsoService>>,
+        Path(provider): Path<String>,
+    ) -> Result<Redirect, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Starting SSO login via {}", provider);
+
+        match sso_service.begin_login(&provider).await {
+            Ok(url) => Ok(Redirect::to(&url)),
+            Err(e) => Err(Self::error_response("SSO login failed", e)),
+        }
+    }
+
+    /// Handle the identity provider redirect
+    pub async fn callback(
+        State(sso_service): State<Arc<SsoService>>,
+        Path(provider): Path<String>,
+        client: SessionClient,
+        Query(params): Query<SsoCallbackQuery>,
+    ) -> Result<Json<LoginResponse>, (StatusCode, Json<ErrorResponse>)> {
+        if let Some(error) = params.error {
+            tracing::warn!("SSO provider {} returned error: {}", provider, error);
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error,
+                    message: params.error_description.unwrap_or_default(),
+                }),
+            ));
+        }
+
+        let (Some(code), Some(state)) = (params.code, params.state) else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid callback".to_string(),
+                    message: "Missing code or state".to_string(),
+                }),
+            ));
+        };
+
+        match sso_service.complete_login(&provider, &code, &state, &client).await {
+            Ok((user, tokens)) => {
+                tracing::info!("User {} logged in via {}", user.username, provider);
+                Ok(Json(LoginResponse {
+                    user,
+                    token: tokens.access_token,
+                    refresh_token: tokens.refresh_token,
+                    token_type: tokens.token_type,
+                    expires_in: tokens.expires_in as u64,
+                    session_id: tokens.session_id,
+                }))
+            }
+            Err(e) => Err(Self::error_response("SSO login failed", e)),
+        }
+    }
+
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::AuthenticationError { .. } => StatusCode::UNAUTHORIZED,
+            HimsError::ValidationError { .. } => StatusCode::NOT_FOUND,
+            HimsError::NetworkError { .. } => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,575 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_jwt::TokenPair;
+use crate::modules::auth::session_service::SessionClient;
+use crate::modules::auth::{AuthService, AuthenticatedUser};
+use crate::modules::authorization::{
+    AuthorizationStorage, HealthcareRelation, PostgresAuthorizationStorage, RelationshipTuple, Resource,
+    Subject,
+};
+use crate::modules::role::RoleService;
+
+// Import SQL queries from separate file
+use crate::modules::auth::auth_sql::*;
+
+/// How long an authorization request may take before the callback arrives
+const PENDING_LOGIN_TTL: Duration = Duration::from_secs(600);
+
+/// Maps an IdP group or app role to HIMS roles and department membership
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SsoGroupMapping {
+    /// Group or role value as it appears in the IdP claim
+    pub group: String,
+    /// HIMS role names (see `modules::role`) assigned on first login
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Department the user becomes a member of on first login
+    pub department_id: Option<Uuid>,
+}
+
+/// OpenID Connect provider (Keycloak realm, Azure AD tenant, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcProviderConfig {
+    /// Issuer URL; discovery is read from `{issuer}/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    #[serde(default = "OidcProviderConfig::default_scopes")]
+    pub scopes: Vec<String>,
+    /// Claim holding group membership; dotted paths such as
+    /// `realm_access.roles` (Keycloak) are supported
+    #[serde(default = "OidcProviderConfig::default_groups_claim")]
+    pub groups_claim: String,
+    #[serde(default)]
+    pub group_mappings: Vec<SsoGroupMapping>,
+    /// Value for the legacy `users.role` column of provisioned users
+    #[serde(default = "OidcProviderConfig::default_user_role")]
+    pub default_user_role: String,
+}
+
+impl OidcProviderConfig {
+    fn default_scopes() -> Vec<String> {
+        vec!["openid".to_string(), "profile".to_string(), "email".to_string()]
+    }
+
+    fn default_groups_claim() -> String {
+        "groups".to_string()
+    }
+
+    fn default_user_role() -> String {
+        "receptionist".to_string()
+    }
+}
+
+/// SSO configuration keyed by provider name (used in the login URL)
+#[derive(Debug, Clone, Default)]
+pub struct SsoConfig {
+    pub providers: HashMap<String, OidcProviderConfig>,
+}
+
+impl SsoConfig {
+    /// Read provider definitions from the `SSO_PROVIDERS` JSON object
+    pub fn from_env() -> Self {
+        Self {
+            providers: std::env::var("SSO_PROVIDERS")
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Subset of the OIDC discovery document used for login
+#[derive(Debug, Clone, Deserialize)]
+struct OidcDiscovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcTokenResponse {
+    id_token: String,
+}
+
+/// Claims read from the ID token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub iss: String,
+    pub exp: i64,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Authorization request awaiting its callback
+struct PendingLogin {
+    provider: String,
+    nonce: String,
+    code_verifier: String,
+    created_at: Instant,
+}
+
+/// Discovery document and signing keys for a provider
+struct ProviderMetadata {
+    discovery: OidcDiscovery,
+    keys: HashMap<String, (DecodingKey, AlgorithmParameters)>,
+}
+
+/// OpenID Connect authorization-code login with PKCE
+pub struct SsoService {
+    pool: PgPool,
+    auth_service: Arc<AuthService>,
+    role_service: RoleService,
+    storage: PostgresAuthorizationStorage,
+    config: SsoConfig,
+    http_client: reqwest::Client,
+    metadata: tokio::sync::RwLock<HashMap<String, ProviderMetadata>>,
+    pending: RwLock<HashMap<String, PendingLogin>>,
+    rng: SystemRandom,
+}
+
+impl SsoService {
+    pub fn new(pool: PgPool, auth_service: Arc<AuthService>, config: SsoConfig) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            storage: PostgresAuthorizationStorage::new(pool.clone()),
+            pool,
+            auth_service,
+            config,
+            http_client: reqwest::Client::new(),
+            metadata: tokio::sync::RwLock::new(HashMap::new()),
+            pending: RwLock::new(HashMap::new()),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Configured provider names
+    pub fn providers(&self) -> Vec<String> {
+        self.config.providers.keys().cloned().collect()
+    }
+
+    /// Build the IdP authorization URL and remember the state for the callback
+    pub async fn begin_login(&self, provider: &str) -> Result<String, HimsError> {
+        let provider_config = self.provider(provider)?;
+        let discovery = self.discovery(provider).await?;
+
+        let state = self.random_token()?;
+        let nonce = self.random_token()?;
+        let code_verifier = self.random_token()?;
+        let code_challenge = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let url = reqwest::Url::parse_with_params(
+            &discovery.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", provider_config.client_id.as_str()),
+                ("redirect_uri", provider_config.redirect_uri.as_str()),
+                ("scope", provider_config.scopes.join(" ").as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| HimsError::ConfigurationError { message: format!("Invalid authorization endpoint: {}", e) })?;
+
+        if let Ok(mut pending) = self.pending.write() {
+            pending.retain(|_, login| login.created_at.elapsed() < PENDING_LOGIN_TTL);
+            pending.insert(state, PendingLogin {
+                provider: provider.to_string(),
+                nonce,
+                code_verifier,
+                created_at: Instant::now(),
+            });
+        }
+
+        Ok(url.to_string())
+    }
+
+    /// Complete the login: exchange the code, validate the ID token, provision
+    /// the user on first login and issue HIMS tokens
+    pub async fn complete_login(
+        &self,
+        provider: &str,
+        code: &str,
+        state: &str,
+        client: &SessionClient,
+    ) -> Result<(AuthenticatedUser, TokenPair), HimsError> {
+        let pending = self
+            .pending
+            .write()
+            .ok()
+            .and_then(|mut pending| pending.remove(state))
+            .filter(|login| login.provider == provider && login.created_at.elapsed() < PENDING_LOGIN_TTL)
+            .ok_or_else(|| HimsError::AuthenticationError {
+                message: "Unknown or expired login state".to_string(),
+            })?;
+
+        let provider_config = self.provider(provider)?;
+        let discovery = self.discovery(provider).await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", provider_config.redirect_uri.as_str()),
+            ("client_id", provider_config.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ];
+        if let Some(secret) = provider_config.client_secret.as_deref() {
+            form.push(("client_secret", secret));
+        }
+
+        let token_response: OidcTokenResponse = self
+            .http_client
+            .post(&discovery.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| HimsError::NetworkError { message: format!("Token exchange failed: {}", e) })?
+            .json()
+            .await
+            .map_err(|e| HimsError::AuthenticationError { message: format!("Invalid token response: {}", e) })?;
+
+        let claims = self.validate_id_token(provider, &token_response.id_token).await?;
+        if claims.nonce.as_deref() != Some(pending.nonce.as_str()) {
+            return Err(HimsError::AuthenticationError {
+                message: "ID token nonce mismatch".to_string(),
+            });
+        }
+
+        let user = self.resolve_user(provider, provider_config, &claims).await?;
+        let tokens = self.auth_service.issue_tokens(&user, client).await?;
+
+        tracing::info!("SSO login via {} for user {}", provider, user.id);
+        Ok((user, tokens))
+    }
+
+    /// Find the linked HIMS user, provisioning one on first login
+    async fn resolve_user(
+        &self,
+        provider: &str,
+        provider_config: &OidcProviderConfig,
+        claims: &IdTokenClaims,
+    ) -> Result<AuthenticatedUser, HimsError> {
+        let now = Utc::now();
+        let claims_json = serde_json::to_value(claims).unwrap_or_default();
+
+        let existing = sqlx::query(GET_SSO_IDENTITY)
+            .bind(provider)
+            .bind(&claims.sub)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let (user_id, username, role) = match existing {
+            Some(row) => {
+                if !row.get::<bool, _>("active") {
+                    return Err(HimsError::AuthenticationError {
+                        message: "User account is disabled".to_string(),
+                    });
+                }
+
+                sqlx::query(TOUCH_SSO_IDENTITY)
+                    .bind(provider)
+                    .bind(&claims.sub)
+                    .bind(now)
+                    .bind(&claims_json)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+                (row.get::<Uuid, _>("user_id"), row.get::<String, _>("username"), row.get::<String, _>("role"))
+            }
+            None => self.provision_user(provider, provider_config, claims, &claims_json).await?,
+        };
+
+        let permissions = self
+            .role_service
+            .get_user_permissions(user_id)
+            .await?
+            .into_iter()
+            .map(|action| action.to_string())
+            .collect();
+
+        Ok(AuthenticatedUser {
+            id: user_id.to_string(),
+            username,
+            role,
+            permissions,
+        })
+    }
+
+    /// Create the user, link the identity and apply group mappings
+    async fn provision_user(
+        &self,
+        provider: &str,
+        provider_config: &OidcProviderConfig,
+        claims: &IdTokenClaims,
+        claims_json: &serde_json::Value,
+    ) -> Result<(Uuid, String, String), HimsError> {
+        let now = Utc::now();
+        let user_id = Uuid::new_v4();
+        let mut username = format!(
+            "{}:{}",
+            provider,
+            claims.preferred_username.as_deref().unwrap_or(&claims.sub)
+        );
+        username.truncate(50);
+        let email = claims.email.clone().unwrap_or_else(|| format!("{}@{}.sso", user_id, provider));
+        let display_name = claims.name.clone().unwrap_or_else(|| username.clone());
+        let role = provider_config.default_user_role.clone();
+
+        let groups = Self::claim_values(claims, &provider_config.groups_claim);
+        let mappings: Vec<&SsoGroupMapping> = provider_config
+            .group_mappings
+            .iter()
+            .filter(|mapping| groups.contains(&mapping.group))
+            .collect();
+
+        let mut tx = self.pool.begin().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(INSERT_SSO_USER)
+            .bind(user_id)
+            .bind(&username)
+            .bind(&email)
+            .bind(&role)
+            .bind(serde_json::json!([{ "use": "official", "text": display_name }]))
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(INSERT_SSO_IDENTITY)
+            .bind(Uuid::new_v4())
+            .bind(provider)
+            .bind(&claims.sub)
+            .bind(user_id)
+            .bind(claims.email.as_ref())
+            .bind(claims_json)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        for role_name in mappings.iter().flat_map(|mapping| mapping.roles.iter()) {
+            sqlx::query(ASSIGN_ROLE_BY_NAME)
+                .bind(Uuid::new_v4())
+                .bind(user_id)
+                .bind(now)
+                .bind(role_name)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        }
+
+        tx.commit().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        for department_id in mappings.iter().filter_map(|mapping| mapping.department_id) {
+            let tuple = RelationshipTuple {
+                object: Resource::Department(department_id),
+                relation: HealthcareRelation::DepartmentMember,
+                subject: Subject::User(user_id),
+                context: Some(format!("sso:{}", provider)),
+                expires_at: None,
+                created_by: Some(user_id),
+                created_at: now,
+                metadata: HashMap::new(),
+            };
+            self.storage.store_relationship(&tuple).await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        }
+
+        tracing::info!(
+            "Provisioned SSO user {} from {} with {} group mappings",
+            user_id, provider, mappings.len()
+        );
+        Ok((user_id, username, role))
+    }
+
+    /// Verify the ID token signature, issuer, audience and expiry
+    async fn validate_id_token(&self, provider: &str, id_token: &str) -> Result<IdTokenClaims, HimsError> {
+        let provider_config = self.provider(provider)?;
+        let header = decode_header(id_token)
+            .map_err(|e| HimsError::AuthenticationError { message: format!("Invalid ID token: {}", e) })?;
+        let kid = header.kid.clone().unwrap_or_default();
+
+        // Refresh signing keys once if the provider rotated them
+        let known = self
+            .metadata
+            .read()
+            .await
+            .get(provider)
+            .map(|metadata| metadata.keys.contains_key(&kid))
+            .unwrap_or(false);
+        if !known {
+            self.metadata.write().await.remove(provider);
+            self.discovery(provider).await?;
+        }
+
+        let metadata = self.metadata.read().await;
+        let metadata = metadata.get(provider).ok_or_else(|| HimsError::ConfigurationError {
+            message: format!("No metadata for provider {}", provider),
+        })?;
+        let (key, params) = metadata.keys.get(&kid).ok_or_else(|| HimsError::AuthenticationError {
+            message: "ID token signed with an unknown key".to_string(),
+        })?;
+
+        let compatible = matches!(
+            (params, header.alg),
+            (AlgorithmParameters::RSA(_), Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512
+                | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512)
+            | (AlgorithmParameters::EllipticCurve(_), Algorithm::ES256 | Algorithm::ES384)
+        );
+        if !compatible {
+            return Err(HimsError::AuthenticationError {
+                message: "ID token algorithm does not match signing key".to_string(),
+            });
+        }
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[metadata.discovery.issuer.as_str()]);
+        validation.set_audience(&[provider_config.client_id.as_str()]);
+        validation.leeway = 60;
+
+        decode::<IdTokenClaims>(id_token, key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| HimsError::AuthenticationError { message: format!("Invalid ID token: {}", e) })
+    }
+
+    /// Load (and cache) discovery metadata and signing keys
+    async fn discovery(&self, provider: &str) -> Result<OidcDiscovery, HimsError> {
+        if let Some(metadata) = self.metadata.read().await.get(provider) {
+            return Ok(metadata.discovery.clone());
+        }
+
+        let provider_config = self.provider(provider)?;
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            provider_config.issuer.trim_end_matches('/')
+        );
+
+        let discovery: OidcDiscovery = self
+            .http_client
+            .get(&discovery_url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| HimsError::NetworkError { message: format!("OIDC discovery failed for {}: {}", provider, e) })?
+            .json()
+            .await
+            .map_err(|e| HimsError::NetworkError { message: format!("Invalid discovery document from {}: {}", provider, e) })?;
+
+        let jwks: JwkSet = self
+            .http_client
+            .get(&discovery.jwks_uri)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| HimsError::NetworkError { message: format!("JWKS fetch failed for {}: {}", provider, e) })?
+            .json()
+            .await
+            .map_err(|e| HimsError::NetworkError { message: format!("Invalid JWKS from {}: {}", provider, e) })?;
+
+        let keys = jwks
+            .keys
+            .iter()
+            .filter_map(|jwk| {
+                let kid = jwk.common.key_id.clone()?;
+                let key = DecodingKey::from_jwk(jwk).ok()?;
+                Some((kid, (key, jwk.algorithm.clone())))
+            })
+            .collect();
+
+        self.metadata.write().await.insert(provider.to_string(), ProviderMetadata {
+            discovery: discovery.clone(),
+            keys,
+        });
+
+        Ok(discovery)
+    }
+
+    fn provider(&self, provider: &str) -> Result<&OidcProviderConfig, HimsError> {
+        self.config.providers.get(provider).ok_or_else(|| HimsError::ValidationError {
+            message: format!("Unknown SSO provider: {}", provider),
+        })
+    }
+
+    fn random_token(&self) -> Result<String, HimsError> {
+        let mut bytes = [0u8; 32];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| HimsError::InternalError { message: "Failed to generate random value".to_string() })?;
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    /// Read a string or string-array claim, following dotted paths
+    pub fn claim_values(claims: &IdTokenClaims, path: &str) -> Vec<String> {
+        let mut segments = path.split('.');
+        let Some(first) = segments.next() else {
+            return Vec::new();
+        };
+
+        let mut value = claims.extra.get(first);
+        for segment in segments {
+            value = value.and_then(|v| v.get(segment));
+        }
+
+        match value {
+            Some(serde_json::Value::String(s)) => vec![s.clone()],
+            Some(serde_json::Value::Array(values)) => values
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_values_follow_dotted_paths() {
+        let claims: IdTokenClaims = serde_json::from_value(serde_json::json!({
+            "sub": "abc",
+            "iss": "https://idp.example.org/realms/hims",
+            "exp": 0,
+            "groups": ["cardiology", "nursing"],
+            "realm_access": { "roles": ["physician"] },
+            "tid": "tenant"
+        }))
+        .unwrap();
+
+        assert_eq!(SsoService::claim_values(&claims, "groups"), vec!["cardiology", "nursing"]);
+        assert_eq!(SsoService::claim_values(&claims, "realm_access.roles"), vec!["physician"]);
+        assert_eq!(SsoService::claim_values(&claims, "tid"), vec!["tenant"]);
+        assert!(SsoService::claim_values(&claims, "missing.path").is_empty());
+    }
+}
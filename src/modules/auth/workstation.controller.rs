@@ -0,0 +1,155 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_controller::{ErrorResponse, LoginResponse};
+use crate::modules::auth::session_service::SessionClient;
+use crate::modules::auth::workstation_service::{
+    EnrollCredentialRequest, EnrolledCredential, WorkstationChallenge, WorkstationService,
+};
+use crate::security::workstation_login::CredentialAssertion;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Smart card and fingerprint login controller for shared workstations
+pub struct WorkstationController {
+    workstation_service: Arc<WorkstationService>,
+}
+
+impl WorkstationController {
+    /// Create new controller with injected service
+    pub fn new(workstation_service: Arc<WorkstationService>) -> Self {
+        Self { workstation_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/workstation/challenge", Self::challenge, "Issue a workstation login challenge")
+            .post("/workstation/login", Self::login, "Log in with a smart card or fingerprint")
+            .post("/workstation/credentials", Self::enroll, "Enroll a smart card or fingerprint for a user")
+            .get(
+                "/workstation/users/:user_id/credentials",
+                Self::list_credentials,
+                "List a user's workstation credentials",
+            )
+            .delete("/workstation/credentials/:id", Self::revoke, "Revoke a workstation credential")
+            .with_state(self.workstation_service.clone())
+    }
+
+    /// Issue a workstation login challenge
+    pub async fn challenge(
+        State(workstation_service): State<Arc<WorkstationService>>,
+    ) -> Result<Json<WorkstationChallenge>, (StatusCode, Json<ErrorResponse>)> {
+        match workstation_service.begin_challenge() {
+            Ok(challenge) => Ok(Json(challenge)),
+            Err(e) => Err(Self::error_response("Failed to issue challenge", e)),
+        }
+    }
+
+    /// Log in with a smart card or fingerprint
+    pub async fn login(
+        State(workstation_service): State<Arc<WorkstationService>>,
+        client: SessionClient,
+        Json(assertion): Json<CredentialAssertion>,
+    ) -> Result<Json<LoginResponse>, (StatusCode, Json<ErrorResponse>)> {
+        match workstation_service.login(&assertion, &client).await {
+            Ok((user, tokens)) => {
+                tracing::info!("User {} logged in with a {}", user.username, assertion.kind.as_str());
+                Ok(Json(LoginResponse {
+                    user,
+                    token: tokens.access_token,
+                    refresh_token: tokens.refresh_token,
+                    token_type: tokens.token_type,
+                    expires_in: tokens.expires_in as u64,
+                    session_id: tokens.session_id,
+                }))
+            }
+            Err(e) => Err(Self::error_response("Workstation login failed", e)),
+        }
+    }
+
+    /// Enroll a smart card or fingerprint for a user
+    pub async fn enroll(
+        State(workstation_service): State<Arc<WorkstationService>>,
+        headers: HeaderMap,
+        Json(request): Json<EnrollCredentialRequest>,
+    ) -> Result<(StatusCode, Json<EnrolledCredential>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        match workstation_service.enroll(&request, actor).await {
+            Ok(credential) => Ok((StatusCode::CREATED, Json(credential))),
+            Err(e) => Err(Self::error_response("Failed to enroll credential", e)),
+        }
+    }
+
+    /// List a user's workstation credentials
+    pub async fn list_credentials(
+        State(workstation_service): State<Arc<WorkstationService>>,
+        headers: HeaderMap,
+        Path(user_id): Path<Uuid>,
+    ) -> Result<Json<Vec<EnrolledCredential>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        match workstation_service.list_for_user(user_id, actor).await {
+            Ok(credentials) => Ok(Json(credentials)),
+            Err(e) => Err(Self::error_response("Failed to list credentials", e)),
+        }
+    }
+
+    /// Revoke a workstation credential
+    pub async fn revoke(
+        State(workstation_service): State<Arc<WorkstationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        match workstation_service.revoke(id, actor).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Not found".to_string(),
+                    message: format!("Workstation credential {} not found", id),
+                }),
+            )),
+            Err(e) => Err(Self::error_response("Failed to revoke credential", e)),
+        }
+    }
+
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::AuthenticationError { .. } => StatusCode::UNAUTHORIZED,
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConfigurationError { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
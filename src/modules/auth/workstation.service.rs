@@ -0,0 +1,622 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{AuditLog, AuditOutcome};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::auth_jwt::TokenPair;
+use crate::modules::auth::session_service::SessionClient;
+use crate::modules::auth::{AuthService, AuthenticatedUser};
+use crate::modules::authorization::Action;
+use crate::modules::role::RoleService;
+use crate::security::workstation_login::{
+    fingerprint_credential_id, smart_card_credential_id, workstation_login_message, CredentialAssertion,
+    CredentialKind,
+};
+
+// Import SQL queries from separate file
+use crate::modules::auth::auth_sql::*;
+
+/// How long a workstation login challenge stays valid
+const WORKSTATION_CHALLENGE_TTL: Duration = Duration::from_secs(120);
+/// Length of an uncompressed P-256 point, as fingerprint device keys are enrolled
+const P256_PUBLIC_KEY_LEN: usize = 65;
+
+/// Signature algorithms PIV Authentication keys sign with, and their issuers
+/// sign certificates with
+static PIV_SIGNATURE_ALGORITHMS: &[&webpki::SignatureAlgorithm] = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+    &webpki::RSA_PKCS1_2048_8192_SHA256,
+];
+
+/// Workstation login settings
+#[derive(Debug, Clone, Default)]
+pub struct WorkstationConfig {
+    /// DER certificates of the CAs PIV cards are issued under; smart card
+    /// login is off without any
+    pub piv_trust_anchors: Vec<Vec<u8>>,
+    /// Whether match-on-device fingerprint readers may log users in
+    pub fingerprint_enabled: bool,
+}
+
+impl WorkstationConfig {
+    /// Settings from `WORKSTATION_PIV_CA_FILE`, a PEM bundle of PIV issuing
+    /// CAs, and `WORKSTATION_FINGERPRINT_ENABLED`
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let piv_trust_anchors = var("WORKSTATION_PIV_CA_FILE")
+            .and_then(|path| match std::fs::read_to_string(&path) {
+                Ok(pem) => Some(pem_certificates(&pem)),
+                Err(e) => {
+                    tracing::error!("Failed to read PIV CA bundle {}: {}", path, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            piv_trust_anchors,
+            fingerprint_enabled: var("WORKSTATION_FINGERPRINT_ENABLED").is_some_and(|value| value == "true"),
+        }
+    }
+}
+
+/// DER certificates in a PEM bundle
+pub fn pem_certificates(pem: &str) -> Vec<Vec<u8>> {
+    let mut certificates = Vec::new();
+    let mut body: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        match line {
+            "-----BEGIN CERTIFICATE-----" => body = Some(String::new()),
+            "-----END CERTIFICATE-----" => {
+                if let Some(der) = body.take().and_then(|b64| general_purpose::STANDARD.decode(b64).ok()) {
+                    certificates.push(der);
+                }
+            }
+            _ => {
+                if let Some(b64) = body.as_mut() {
+                    b64.push_str(line);
+                }
+            }
+        }
+    }
+    certificates
+}
+
+/// A smart card or fingerprint enrolled for a user
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrolledCredential {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: CredentialKind,
+    pub credential_id: String,
+    /// Certificate (smart cards) or device key (fingerprints) signatures are
+    /// verified with
+    #[serde(skip)]
+    pub public_key: Vec<u8>,
+    pub name: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+fn credential_from_row(row: &PgRow) -> EnrolledCredential {
+    EnrolledCredential {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        kind: CredentialKind::from_string(row.get("kind")).unwrap_or(CredentialKind::SmartCard),
+        credential_id: row.get("credential_id"),
+        public_key: row.get("public_key"),
+        name: row.get("name"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        last_used_at: row.get("last_used_at"),
+    }
+}
+
+/// A smart card or fingerprint to enroll; binary values are base64url
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnrollCredentialRequest {
+    pub user_id: Uuid,
+    pub kind: CredentialKind,
+    /// PIV Authentication certificate, DER (smart cards)
+    pub certificate: Option<String>,
+    /// Reader, enrolled template and the reader's uncompressed P-256 device
+    /// key (fingerprints)
+    pub device_id: Option<String>,
+    pub template_id: Option<String>,
+    pub public_key: Option<String>,
+    pub name: Option<String>,
+}
+
+/// A one-time challenge a workstation answers with a credential
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkstationChallenge {
+    pub challenge: String,
+    pub expires_in: u64,
+}
+
+fn decode_field(field: &str, value: Option<&str>) -> Result<Vec<u8>, HimsError> {
+    value
+        .and_then(|value| general_purpose::URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok())
+        .filter(|bytes| !bytes.is_empty())
+        .ok_or_else(|| HimsError::ValidationError {
+            message: format!("Missing or invalid {}", field),
+        })
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Checks one kind of workstation credential: what it is enrolled under, what
+/// is kept to verify it, and its answers to login challenges
+pub trait CredentialAdapter: Send + Sync {
+    fn kind(&self) -> CredentialKind;
+
+    /// ID the credential answering a challenge is enrolled under
+    fn credential_id(&self, assertion: &CredentialAssertion) -> Result<String, HimsError>;
+
+    /// Check a credential being enrolled, returning its ID and the key material
+    /// kept to verify it
+    fn enroll(&self, request: &EnrollCredentialRequest) -> Result<(String, Vec<u8>), HimsError>;
+
+    /// Verify the credential's signature of a login message
+    fn verify(&self, credential: &EnrolledCredential, message: &[u8], signature: &[u8]) -> Result<(), HimsError>;
+}
+
+/// PIV smart cards in CCID readers. Certificates must chain to a configured
+/// issuing CA, be valid for client authentication, and be in date each time
+/// they are used.
+pub struct PivSmartCardAdapter {
+    trust_anchors: Vec<Vec<u8>>,
+}
+
+impl PivSmartCardAdapter {
+    pub fn new(trust_anchors: Vec<Vec<u8>>) -> Self {
+        Self { trust_anchors }
+    }
+
+    fn validate_certificate(&self, der: &[u8]) -> Result<(), HimsError> {
+        let invalid = |reason: String| HimsError::AuthenticationError {
+            message: format!("Smart card certificate rejected: {}", reason),
+        };
+        let certificate = webpki::EndEntityCert::try_from(der).map_err(|e| invalid(format!("{:?}", e)))?;
+        let anchors: Vec<webpki::TrustAnchor> = self
+            .trust_anchors
+            .iter()
+            .filter_map(|ca| webpki::TrustAnchor::try_from_cert_der(ca).ok())
+            .collect();
+        let now = webpki::Time::from_seconds_since_unix_epoch(Utc::now().timestamp().max(0) as u64);
+        certificate
+            .verify_for_usage(PIV_SIGNATURE_ALGORITHMS, &anchors, &[], now, webpki::KeyUsage::client_auth(), &[])
+            .map_err(|e| invalid(format!("{:?}", e)))
+    }
+}
+
+impl CredentialAdapter for PivSmartCardAdapter {
+    fn kind(&self) -> CredentialKind {
+        CredentialKind::SmartCard
+    }
+
+    fn credential_id(&self, assertion: &CredentialAssertion) -> Result<String, HimsError> {
+        let certificate = decode_field("certificate", assertion.certificate.as_deref())?;
+        Ok(smart_card_credential_id(&certificate))
+    }
+
+    fn enroll(&self, request: &EnrollCredentialRequest) -> Result<(String, Vec<u8>), HimsError> {
+        let certificate = decode_field("certificate", request.certificate.as_deref())?;
+        self.validate_certificate(&certificate).map_err(|e| HimsError::ValidationError {
+            message: e.to_string(),
+        })?;
+        Ok((smart_card_credential_id(&certificate), certificate))
+    }
+
+    fn verify(&self, credential: &EnrolledCredential, message: &[u8], signature: &[u8]) -> Result<(), HimsError> {
+        self.validate_certificate(&credential.public_key)?;
+        let certificate = webpki::EndEntityCert::try_from(credential.public_key.as_slice()).map_err(|e| {
+            HimsError::AuthenticationError {
+                message: format!("Smart card certificate rejected: {:?}", e),
+            }
+        })?;
+        if PIV_SIGNATURE_ALGORITHMS
+            .iter()
+            .any(|algorithm| certificate.verify_signature(algorithm, message, signature).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(HimsError::AuthenticationError {
+                message: "Smart card signature is invalid".to_string(),
+            })
+        }
+    }
+}
+
+/// Match-on-device fingerprint readers, which keep templates on the device
+/// and sign challenges with a P-256 device key once a finger matches
+pub struct FingerprintDeviceAdapter;
+
+impl CredentialAdapter for FingerprintDeviceAdapter {
+    fn kind(&self) -> CredentialKind {
+        CredentialKind::Fingerprint
+    }
+
+    fn credential_id(&self, assertion: &CredentialAssertion) -> Result<String, HimsError> {
+        match (assertion.device_id.as_deref(), assertion.template_id.as_deref()) {
+            (Some(device_id), Some(template_id)) => Ok(fingerprint_credential_id(device_id, template_id)),
+            _ => Err(HimsError::ValidationError {
+                message: "Missing fingerprint device or template".to_string(),
+            }),
+        }
+    }
+
+    fn enroll(&self, request: &EnrollCredentialRequest) -> Result<(String, Vec<u8>), HimsError> {
+        let (Some(device_id), Some(template_id)) = (request.device_id.as_deref(), request.template_id.as_deref())
+        else {
+            return Err(HimsError::ValidationError {
+                message: "Missing fingerprint device or template".to_string(),
+            });
+        };
+        if device_id.is_empty() || device_id.contains(':') || template_id.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Invalid fingerprint device or template".to_string(),
+            });
+        }
+        let public_key = decode_field("public_key", request.public_key.as_deref())?;
+        if public_key.len() != P256_PUBLIC_KEY_LEN || public_key[0] != 0x04 {
+            return Err(HimsError::ValidationError {
+                message: "Fingerprint device key must be an uncompressed P-256 point".to_string(),
+            });
+        }
+        Ok((fingerprint_credential_id(device_id, template_id), public_key))
+    }
+
+    fn verify(&self, credential: &EnrolledCredential, message: &[u8], signature: &[u8]) -> Result<(), HimsError> {
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &credential.public_key)
+            .verify(message, signature)
+            .map_err(|_| HimsError::AuthenticationError {
+                message: "Fingerprint device signature is invalid".to_string(),
+            })
+    }
+}
+
+/// Smart card and fingerprint login for shared clinical workstations. Users
+/// tap a badge or a finger instead of typing a password; the verified
+/// credential is mapped to the user it was enrolled for, who gets the same
+/// session and tokens as a password login.
+pub struct WorkstationService {
+    pool: PgPool,
+    auth_service: Arc<AuthService>,
+    role_service: RoleService,
+    audit_service: AuditService,
+    adapters: HashMap<&'static str, Arc<dyn CredentialAdapter>>,
+    challenges: RwLock<HashMap<String, Instant>>,
+    rng: SystemRandom,
+}
+
+impl WorkstationService {
+    pub fn new(pool: PgPool, auth_service: Arc<AuthService>, config: WorkstationConfig) -> Self {
+        let mut adapters: Vec<Arc<dyn CredentialAdapter>> = Vec::new();
+        if config.piv_trust_anchors.is_empty() {
+            tracing::warn!("No PIV issuing CAs configured; smart card login is disabled");
+        } else {
+            adapters.push(Arc::new(PivSmartCardAdapter::new(config.piv_trust_anchors)));
+        }
+        if config.fingerprint_enabled {
+            adapters.push(Arc::new(FingerprintDeviceAdapter));
+        }
+        Self::with_adapters(pool, auth_service, adapters)
+    }
+
+    /// Create the service with the credential kinds it accepts
+    pub fn with_adapters(
+        pool: PgPool,
+        auth_service: Arc<AuthService>,
+        adapters: Vec<Arc<dyn CredentialAdapter>>,
+    ) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            audit_service: AuditService::new(pool.clone()),
+            pool,
+            auth_service,
+            adapters: adapters.into_iter().map(|adapter| (adapter.kind().as_str(), adapter)).collect(),
+            challenges: RwLock::new(HashMap::new()),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    fn adapter(&self, kind: CredentialKind) -> Result<&Arc<dyn CredentialAdapter>, HimsError> {
+        self.adapters.get(kind.as_str()).ok_or_else(|| HimsError::ConfigurationError {
+            message: format!("{} login is not enabled", kind.as_str()),
+        })
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn audit(&self, log: AuditLog) {
+        if let Err(e) = self.audit_service.create_audit_log(&log).await {
+            tracing::error!("Failed to write workstation login audit event: {}", e);
+        }
+    }
+
+    /// Issue a one-time login challenge
+    pub fn begin_challenge(&self) -> Result<WorkstationChallenge, HimsError> {
+        let mut bytes = [0u8; 32];
+        self.rng.fill(&mut bytes).map_err(|_| HimsError::InternalError {
+            message: "Failed to generate challenge".to_string(),
+        })?;
+        let challenge = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+
+        let mut challenges = self.challenges.write().map_err(|_| HimsError::InternalError {
+            message: "Challenge store poisoned".to_string(),
+        })?;
+        challenges.retain(|_, issued_at| issued_at.elapsed() < WORKSTATION_CHALLENGE_TTL);
+        challenges.insert(challenge.clone(), Instant::now());
+
+        Ok(WorkstationChallenge {
+            challenge,
+            expires_in: WORKSTATION_CHALLENGE_TTL.as_secs(),
+        })
+    }
+
+    /// Log in with a credential's answer to a challenge. The challenge is
+    /// used up whether or not the login succeeds.
+    pub async fn login(
+        &self,
+        assertion: &CredentialAssertion,
+        client: &SessionClient,
+    ) -> Result<(AuthenticatedUser, TokenPair), HimsError> {
+        let issued = self
+            .challenges
+            .write()
+            .ok()
+            .and_then(|mut challenges| challenges.remove(&assertion.challenge))
+            .filter(|issued_at| issued_at.elapsed() < WORKSTATION_CHALLENGE_TTL);
+        if issued.is_none() {
+            return Err(HimsError::AuthenticationError {
+                message: "Unknown or expired login challenge".to_string(),
+            });
+        }
+
+        let adapter = self.adapter(assertion.kind)?;
+        let credential_id = adapter.credential_id(assertion)?;
+        let signature = decode_field("signature", Some(&assertion.signature))?;
+
+        let row = sqlx::query(GET_WORKSTATION_CREDENTIAL)
+            .bind(assertion.kind.as_str())
+            .bind(&credential_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let Some(row) = row else {
+            self.audit(
+                AuditLog::authentication_event(None, AuditOutcome::MinorFailure)
+                    .with_source_info(client.ip_address.clone(), client.user_agent.clone())
+                    .with_details(format!("workstation_login_unknown_credential {}", assertion.kind.as_str())),
+            )
+            .await;
+            return Err(HimsError::AuthenticationError {
+                message: "Credential is not enrolled".to_string(),
+            });
+        };
+        let credential = credential_from_row(&row);
+        let event = |outcome: AuditOutcome, detail: &str| {
+            AuditLog::authentication_event(Some(credential.user_id), outcome)
+                .with_source_info(client.ip_address.clone(), client.user_agent.clone())
+                .with_details(format!("{} {} {}", detail, credential.kind.as_str(), credential.id))
+        };
+
+        let now = Utc::now();
+        let locked_until = row.get::<Option<DateTime<Utc>>, _>("locked_until").filter(|until| *until > now);
+        if !row.get::<bool, _>("active") || locked_until.is_some() {
+            self.audit(event(AuditOutcome::SeriousFailure, "workstation_login_rejected_account")).await;
+            return Err(HimsError::AuthenticationError {
+                message: "User account is disabled or locked".to_string(),
+            });
+        }
+
+        let message = workstation_login_message(assertion.challenge.clone(), credential_id);
+        if let Err(e) = adapter.verify(&credential, &message, &signature) {
+            self.audit(event(AuditOutcome::SeriousFailure, "workstation_login_failed")).await;
+            return Err(e);
+        }
+
+        sqlx::query(RECORD_SUCCESSFUL_LOGIN)
+            .bind(credential.user_id)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+        sqlx::query(TOUCH_WORKSTATION_CREDENTIAL)
+            .bind(credential.id)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+        self.audit(event(AuditOutcome::Success, "workstation_login_succeeded")).await;
+
+        let permissions = self
+            .role_service
+            .get_user_permissions(credential.user_id)
+            .await?
+            .into_iter()
+            .map(|action| action.to_string())
+            .collect();
+        let user = AuthenticatedUser {
+            id: credential.user_id.to_string(),
+            username: row.get("username"),
+            role: row.get("role"),
+            permissions,
+        };
+        let tokens = self.auth_service.issue_tokens(&user, client).await?;
+
+        tracing::info!("Workstation {} login for user {}", credential.kind.as_str(), user.id);
+        Ok((user, tokens))
+    }
+
+    /// Enroll a smart card or fingerprint for a user
+    pub async fn enroll(
+        &self,
+        request: &EnrollCredentialRequest,
+        actor: Uuid,
+    ) -> Result<EnrolledCredential, HimsError> {
+        self.require(actor, &[Action::ManageUsers]).await?;
+        let (credential_id, public_key) = self.adapter(request.kind)?.enroll(request)?;
+
+        let row = sqlx::query(INSERT_WORKSTATION_CREDENTIAL)
+            .bind(Uuid::new_v4())
+            .bind(request.user_id)
+            .bind(request.kind.as_str())
+            .bind(&credential_id)
+            .bind(&public_key)
+            .bind(&request.name)
+            .bind(actor)
+            .bind(Utc::now())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ValidationError {
+                    message: "Credential is already enrolled".to_string(),
+                },
+                e => database_error(e),
+            })?;
+        let credential = credential_from_row(&row);
+
+        self.audit(
+            AuditLog::authentication_event(Some(credential.user_id), AuditOutcome::Success)
+                .with_user(actor)
+                .with_details(format!(
+                    "workstation_credential_enrolled {} {}",
+                    credential.kind.as_str(),
+                    credential.id
+                )),
+        )
+        .await;
+        Ok(credential)
+    }
+
+    /// A user's enrolled credentials; users may list their own
+    pub async fn list_for_user(&self, user_id: Uuid, actor: Uuid) -> Result<Vec<EnrolledCredential>, HimsError> {
+        if user_id != actor {
+            self.require(actor, &[Action::ManageUsers]).await?;
+        }
+        let rows = sqlx::query(LIST_USER_WORKSTATION_CREDENTIALS)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(credential_from_row).collect())
+    }
+
+    /// Revoke a credential, such as a lost badge; returns false if it was
+    /// not enrolled
+    pub async fn revoke(&self, id: Uuid, actor: Uuid) -> Result<bool, HimsError> {
+        self.require(actor, &[Action::ManageUsers]).await?;
+        let row = sqlx::query(REVOKE_WORKSTATION_CREDENTIAL)
+            .bind(id)
+            .bind(Utc::now())
+            .bind(actor)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let Some(row) = row else {
+            return Ok(false);
+        };
+        self.audit(
+            AuditLog::authentication_event(Some(row.get("user_id")), AuditOutcome::Success)
+                .with_user(actor)
+                .with_details(format!("workstation_credential_revoked {}", id)),
+        )
+        .await;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    fn enrolled(public_key: Vec<u8>, credential_id: &str) -> EnrolledCredential {
+        EnrolledCredential {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            kind: CredentialKind::Fingerprint,
+            credential_id: credential_id.to_string(),
+            public_key,
+            name: None,
+            created_by: Uuid::new_v4(),
+            created_at: Utc::now(),
+            last_used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_device_signature() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let device_key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+
+        let request = EnrollCredentialRequest {
+            user_id: Uuid::new_v4(),
+            kind: CredentialKind::Fingerprint,
+            certificate: None,
+            device_id: Some("reader-ward-3".to_string()),
+            template_id: Some("7".to_string()),
+            public_key: Some(general_purpose::URL_SAFE_NO_PAD.encode(device_key.public_key().as_ref())),
+            name: None,
+        };
+        let (credential_id, public_key) = FingerprintDeviceAdapter.enroll(&request).unwrap();
+        assert_eq!(credential_id, "reader-ward-3:7");
+        let credential = enrolled(public_key, &credential_id);
+
+        let message = workstation_login_message("challenge".to_string(), credential_id.clone());
+        let signature = device_key.sign(&rng, &message).unwrap();
+        assert!(FingerprintDeviceAdapter.verify(&credential, &message, signature.as_ref()).is_ok());
+
+        // A signature over another challenge, or for another template, is refused
+        let other = workstation_login_message("other".to_string(), credential_id);
+        assert!(FingerprintDeviceAdapter.verify(&credential, &other, signature.as_ref()).is_err());
+        let replayed = workstation_login_message("challenge".to_string(), "reader-ward-3:8".to_string());
+        assert!(FingerprintDeviceAdapter.verify(&credential, &replayed, signature.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_smart_card_enrollment_requires_trusted_certificate() {
+        let pem = "-----BEGIN CERTIFICATE-----\nAAEC\n-----END CERTIFICATE-----\n";
+        assert_eq!(pem_certificates(pem), vec![vec![0, 1, 2]]);
+
+        let adapter = PivSmartCardAdapter::new(Vec::new());
+        let request = EnrollCredentialRequest {
+            user_id: Uuid::new_v4(),
+            kind: CredentialKind::SmartCard,
+            certificate: Some(general_purpose::URL_SAFE_NO_PAD.encode(b"not a certificate")),
+            device_id: None,
+            template_id: None,
+            public_key: None,
+            name: None,
+        };
+        assert!(matches!(adapter.enroll(&request), Err(HimsError::ValidationError { .. })));
+    }
+}
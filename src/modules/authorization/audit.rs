@@ -142,7 +142,7 @@ impl AuditEntry {
     /// Check if this was an emergency access
     pub fn is_emergency_access(&self) -> bool {
         matches!(self.decision, AccessDecision::EmergencyAccess | AccessDecision::BreakGlassAccess)
-            || self.emergency_context.as_ref().map_or(false, |e| e.is_emergency)
+            || self.emergency_context.as_ref().is_some_and(|e| e.is_emergency)
     }
     
     /// Get a summary of the audit entry for logging
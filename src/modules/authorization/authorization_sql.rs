@@ -41,6 +41,24 @@ pub mod relationships {
         )
     "#;
 
+    /// Check many relationship tuples at once. Parameters are parallel arrays;
+    /// returns the (1-based) ordinal of every tuple that exists
+    pub const CHECK_RELATIONSHIPS_BATCH: &str = r#"
+        SELECT t.ordinal
+        FROM UNNEST($1::text[], $2::uuid[], $3::text[], $4::text[], $5::uuid[])
+            WITH ORDINALITY AS t(resource_type, resource_id, relation, subject_type, subject_id, ordinal)
+        WHERE EXISTS(
+            SELECT 1 FROM authorization_relations r
+            WHERE r.resource_type = t.resource_type
+            AND r.resource_id = t.resource_id
+            AND r.relation = t.relation
+            AND r.subject_type = t.subject_type
+            AND r.subject_id = t.subject_id
+            AND r.is_active = true
+            AND (r.expires_at IS NULL OR r.expires_at > CURRENT_TIMESTAMP)
+        )
+    "#;
+
     /// Find direct relationships for a resource
     pub const FIND_RELATIONSHIPS_FOR_RESOURCE: &str = r#"
         SELECT subject_type, subject_id, relation, created_at, metadata
@@ -64,6 +82,75 @@ pub mod relationships {
         ORDER BY created_at DESC
     "#;
 
+    /// Find subjects that inherit a relation through group membership.
+    ///
+    /// Starts from the subjects directly related to the resource and walks
+    /// department/organization/group membership edges downwards. The `path`
+    /// array guards against membership cycles and `$4` bounds the depth.
+    pub const FIND_INHERITED_RELATIONSHIPS: &str = r#"
+        WITH RECURSIVE subject_graph AS (
+            SELECT subject_type, subject_id, 1 AS depth,
+                   ARRAY[subject_type || ':' || subject_id::text] AS path
+            FROM authorization_relations
+            WHERE resource_type = $1
+            AND resource_id = $2
+            AND relation = $3
+            AND is_active = true
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+
+            UNION ALL
+
+            SELECT r.subject_type, r.subject_id, sg.depth + 1,
+                   sg.path || (r.subject_type || ':' || r.subject_id::text)
+            FROM subject_graph sg
+            JOIN authorization_relations r
+                ON r.resource_type = sg.subject_type
+                AND r.resource_id = sg.subject_id
+            WHERE sg.subject_type IN ('department', 'organization', 'group')
+            AND r.relation IN ('department_member', 'department_head')
+            AND r.is_active = true
+            AND (r.expires_at IS NULL OR r.expires_at > CURRENT_TIMESTAMP)
+            AND sg.depth < $4
+            AND NOT (r.subject_type || ':' || r.subject_id::text) = ANY(sg.path)
+        )
+        SELECT DISTINCT subject_type, subject_id
+        FROM subject_graph
+        WHERE depth > 1
+    "#;
+
+    /// Get every department, organization and group a subject belongs to,
+    /// directly or transitively, bounded by `$3` levels
+    pub const GET_SUBJECT_HIERARCHY: &str = r#"
+        WITH RECURSIVE hierarchy AS (
+            SELECT resource_type, resource_id, 1 AS depth,
+                   ARRAY[$1::text || ':' || $2::text, resource_type || ':' || resource_id::text] AS path
+            FROM authorization_relations
+            WHERE subject_type = $1
+            AND subject_id = $2
+            AND relation IN ('department_member', 'department_head')
+            AND resource_type IN ('department', 'organization', 'group')
+            AND is_active = true
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+
+            UNION ALL
+
+            SELECT r.resource_type, r.resource_id, h.depth + 1,
+                   h.path || (r.resource_type || ':' || r.resource_id::text)
+            FROM hierarchy h
+            JOIN authorization_relations r
+                ON r.subject_type = h.resource_type
+                AND r.subject_id = h.resource_id
+            WHERE r.relation IN ('department_member', 'department_head')
+            AND r.resource_type IN ('department', 'organization', 'group')
+            AND r.is_active = true
+            AND (r.expires_at IS NULL OR r.expires_at > CURRENT_TIMESTAMP)
+            AND h.depth < $3
+            AND NOT (r.resource_type || ':' || r.resource_id::text) = ANY(h.path)
+        )
+        SELECT DISTINCT resource_type, resource_id
+        FROM hierarchy
+    "#;
+
     /// Get all active relationships (for admin/debugging)
     pub const GET_ALL_ACTIVE_RELATIONSHIPS: &str = r#"
         SELECT id, resource_type, resource_id, relation, subject_type, subject_id, 
@@ -85,6 +172,24 @@ pub mod relationships {
     "#;
 }
 
+/// SQL queries for the relationship changelog (Watch API)
+pub mod changelog {
+    /// Read changes recorded after a revision
+    pub const GET_CHANGES_SINCE: &str = r#"
+        SELECT revision, operation, resource_type, resource_id, relation,
+               subject_type, subject_id, changed_by, changed_at
+        FROM authorization_changelog
+        WHERE revision > $1
+        ORDER BY revision ASC
+        LIMIT $2
+    "#;
+
+    /// Get the latest recorded revision
+    pub const GET_CURRENT_REVISION: &str = r#"
+        SELECT COALESCE(MAX(revision), 0) FROM authorization_changelog
+    "#;
+}
+
 /// SQL queries for policy management
 pub mod policies {
     /// Store a new policy
@@ -323,7 +428,7 @@ pub mod sessions {
         INSERT INTO authorization_session_context (
             session_id, user_id, department_id, location_id, shift_id,
             ip_address, user_agent, expires_at, mfa_verified, risk_score, context_data
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ) VALUES ($1, $2, $3, $4, $5, $6::inet, $7, $8, $9, $10, $11)
         ON CONFLICT (session_id) 
         DO UPDATE SET 
             last_activity = CURRENT_TIMESTAMP,
@@ -342,8 +447,8 @@ pub mod sessions {
     /// Get session context
     pub const GET_SESSION_CONTEXT: &str = r#"
         SELECT id, session_id, user_id, department_id, location_id, shift_id,
-               ip_address, user_agent, created_at, last_activity, expires_at,
-               is_active, mfa_verified, risk_score, context_data
+               ip_address::text AS ip_address, user_agent, created_at, last_activity, expires_at,
+               is_active, mfa_verified, mfa_verified_at, risk_score::real AS risk_score, context_data
         FROM authorization_session_context 
         WHERE session_id = $1 
         AND is_active = true 
@@ -358,6 +463,15 @@ pub mod sessions {
         AND is_active = true
     "#;
 
+    /// Record a successful MFA verification for the session
+    pub const MARK_SESSION_MFA_VERIFIED: &str = r#"
+        UPDATE authorization_session_context 
+        SET mfa_verified = true, mfa_verified_at = $2, last_activity = CURRENT_TIMESTAMP 
+        WHERE session_id = $1 
+        AND user_id = $3 
+        AND is_active = true
+    "#;
+
     /// Invalidate session
     pub const INVALIDATE_SESSION: &str = r#"
         UPDATE authorization_session_context 
@@ -373,11 +487,39 @@ pub mod sessions {
         AND is_active = true
     "#;
 
+    /// Invalidate every session held by a user ("log out everywhere")
+    pub const INVALIDATE_USER_SESSIONS: &str = r#"
+        UPDATE authorization_session_context 
+        SET is_active = false 
+        WHERE user_id = $1 
+        AND is_active = true
+    "#;
+
+    /// Get all active sessions, most recently used first
+    pub const GET_ACTIVE_SESSIONS: &str = r#"
+        SELECT id, session_id, user_id, department_id, location_id, shift_id,
+               ip_address::text AS ip_address, user_agent, created_at, last_activity, 
+               expires_at, mfa_verified, mfa_verified_at, risk_score::real AS risk_score
+        FROM authorization_session_context 
+        WHERE is_active = true 
+        AND expires_at > CURRENT_TIMESTAMP
+        ORDER BY last_activity DESC
+        LIMIT $1 OFFSET $2
+    "#;
+
+    /// Deactivate sessions idle since before the cutoff
+    pub const CLEANUP_IDLE_SESSIONS: &str = r#"
+        UPDATE authorization_session_context 
+        SET is_active = false 
+        WHERE last_activity < $1 
+        AND is_active = true
+    "#;
+
     /// Get user's active sessions
     pub const GET_USER_ACTIVE_SESSIONS: &str = r#"
-        SELECT id, session_id, department_id, location_id, shift_id,
-               ip_address, user_agent, created_at, last_activity, 
-               expires_at, mfa_verified, risk_score
+        SELECT id, session_id, user_id, department_id, location_id, shift_id,
+               ip_address::text AS ip_address, user_agent, created_at, last_activity, 
+               expires_at, mfa_verified, mfa_verified_at, risk_score::real AS risk_score
         FROM authorization_session_context 
         WHERE user_id = $1 
         AND is_active = true 
@@ -387,9 +529,9 @@ pub mod sessions {
 
     /// Get high-risk sessions
     pub const GET_HIGH_RISK_SESSIONS: &str = r#"
-        SELECT id, session_id, user_id, department_id, location_id,
-               ip_address, user_agent, created_at, last_activity, 
-               risk_score, context_data
+        SELECT id, session_id, user_id, department_id, location_id, shift_id,
+               ip_address::text AS ip_address, user_agent, created_at, last_activity, 
+               expires_at, mfa_verified, mfa_verified_at, risk_score::real AS risk_score, context_data
         FROM authorization_session_context 
         WHERE risk_score >= $1 
         AND is_active = true 
@@ -469,4 +611,318 @@ pub mod admin {
         GROUP BY DATE_TRUNC('hour', timestamp), decision
         ORDER BY hour DESC, count DESC
     "#;
-}
\ No newline at end of file
+}
+/// SQL for the SQLite storage backend used by the desktop app.
+///
+/// Identifiers are stored as TEXT and timestamps as RFC 3339 strings, so
+/// expiry checks bind the current time instead of using `CURRENT_TIMESTAMP`.
+pub mod sqlite {
+    /// Create the authorization schema if it does not exist
+    pub const CREATE_SCHEMA: &str = r#"
+        CREATE TABLE IF NOT EXISTS authorization_relations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            resource_type TEXT NOT NULL,
+            resource_id TEXT NOT NULL,
+            relation TEXT NOT NULL,
+            subject_type TEXT NOT NULL,
+            subject_id TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            created_by TEXT,
+            metadata TEXT NOT NULL DEFAULT '{}',
+            expires_at TEXT,
+            is_active INTEGER NOT NULL DEFAULT 1
+        );
+
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_authorization_relations_unique
+            ON authorization_relations (resource_type, resource_id, relation, subject_type, subject_id)
+            WHERE is_active = 1;
+
+        CREATE INDEX IF NOT EXISTS idx_authorization_relations_subject
+            ON authorization_relations (subject_type, subject_id);
+
+        CREATE TABLE IF NOT EXISTS authorization_policies (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            policy_type TEXT NOT NULL,
+            conditions TEXT NOT NULL DEFAULT '[]',
+            effect TEXT NOT NULL,
+            priority INTEGER NOT NULL DEFAULT 0,
+            is_active INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            metadata TEXT NOT NULL DEFAULT '{}'
+        );
+
+        CREATE TABLE IF NOT EXISTS authorization_audit_log (
+            id TEXT PRIMARY KEY,
+            user_id TEXT,
+            action TEXT NOT NULL,
+            resource_type TEXT NOT NULL,
+            resource_id TEXT NOT NULL,
+            decision TEXT NOT NULL,
+            reasons TEXT NOT NULL DEFAULT '[]',
+            ip_address TEXT,
+            user_agent TEXT,
+            session_id TEXT,
+            metadata TEXT NOT NULL DEFAULT '{}',
+            timestamp TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS authorization_changelog (
+            revision INTEGER PRIMARY KEY AUTOINCREMENT,
+            operation TEXT NOT NULL CHECK (operation IN ('added', 'removed')),
+            resource_type TEXT NOT NULL,
+            resource_id TEXT NOT NULL,
+            relation TEXT NOT NULL,
+            subject_type TEXT NOT NULL,
+            subject_id TEXT NOT NULL,
+            changed_by TEXT,
+            changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+
+        CREATE TRIGGER IF NOT EXISTS authorization_relations_added
+            AFTER INSERT ON authorization_relations
+            WHEN NEW.is_active = 1
+        BEGIN
+            INSERT INTO authorization_changelog
+                (operation, resource_type, resource_id, relation, subject_type, subject_id, changed_by)
+            VALUES
+                ('added', NEW.resource_type, NEW.resource_id, NEW.relation, NEW.subject_type, NEW.subject_id, NEW.created_by);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS authorization_relations_removed
+            AFTER UPDATE OF is_active ON authorization_relations
+            WHEN OLD.is_active = 1 AND NEW.is_active = 0
+        BEGIN
+            INSERT INTO authorization_changelog
+                (operation, resource_type, resource_id, relation, subject_type, subject_id)
+            VALUES
+                ('removed', NEW.resource_type, NEW.resource_id, NEW.relation, NEW.subject_type, NEW.subject_id);
+        END;
+    "#;
+
+    /// Insert a new relationship tuple
+    pub const INSERT_RELATIONSHIP: &str = r#"
+        INSERT INTO authorization_relations (
+            resource_type, resource_id, relation, subject_type, subject_id,
+            created_by, metadata, expires_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+    "#;
+
+    /// Remove a relationship tuple
+    pub const REMOVE_RELATIONSHIP: &str = r#"
+        UPDATE authorization_relations
+        SET is_active = 0
+        WHERE resource_type = ?1
+        AND resource_id = ?2
+        AND relation = ?3
+        AND subject_type = ?4
+        AND subject_id = ?5
+        AND is_active = 1
+    "#;
+
+    /// Check if a relationship exists (`?6` is the current time)
+    pub const CHECK_RELATIONSHIP: &str = r#"
+        SELECT EXISTS(
+            SELECT 1 FROM authorization_relations
+            WHERE resource_type = ?1
+            AND resource_id = ?2
+            AND relation = ?3
+            AND subject_type = ?4
+            AND subject_id = ?5
+            AND is_active = 1
+            AND (expires_at IS NULL OR expires_at > ?6)
+        )
+    "#;
+
+    /// Get active relationships for a resource (`?3` is the current time)
+    pub const GET_RELATIONSHIPS_FOR_RESOURCE: &str = r#"
+        SELECT resource_type, resource_id, relation, subject_type, subject_id,
+               metadata, expires_at, created_by, created_at
+        FROM authorization_relations
+        WHERE resource_type = ?1 AND resource_id = ?2
+        AND is_active = 1
+        AND (expires_at IS NULL OR expires_at > ?3)
+    "#;
+
+    /// Get active relationships for a subject (`?3` is the current time)
+    pub const GET_RELATIONSHIPS_FOR_SUBJECT: &str = r#"
+        SELECT resource_type, resource_id, relation, subject_type, subject_id,
+               metadata, expires_at, created_by, created_at
+        FROM authorization_relations
+        WHERE subject_type = ?1 AND subject_id = ?2
+        AND is_active = 1
+        AND (expires_at IS NULL OR expires_at > ?3)
+    "#;
+
+    /// Find direct subjects for a resource and relation (`?4` is the current time)
+    pub const FIND_DIRECT_RELATIONSHIPS: &str = r#"
+        SELECT subject_type, subject_id
+        FROM authorization_relations
+        WHERE resource_type = ?1
+        AND resource_id = ?2
+        AND relation = ?3
+        AND is_active = 1
+        AND (expires_at IS NULL OR expires_at > ?4)
+        ORDER BY created_at DESC
+    "#;
+
+    /// Find subjects inheriting a relation through group membership.
+    /// `?4` bounds the depth and `?5` is the current time; `path` guards against cycles.
+    pub const FIND_INHERITED_RELATIONSHIPS: &str = r#"
+        WITH RECURSIVE subject_graph(subject_type, subject_id, depth, path) AS (
+            SELECT subject_type, subject_id, 1,
+                   '|' || subject_type || ':' || subject_id || '|'
+            FROM authorization_relations
+            WHERE resource_type = ?1
+            AND resource_id = ?2
+            AND relation = ?3
+            AND is_active = 1
+            AND (expires_at IS NULL OR expires_at > ?5)
+
+            UNION ALL
+
+            SELECT r.subject_type, r.subject_id, sg.depth + 1,
+                   sg.path || r.subject_type || ':' || r.subject_id || '|'
+            FROM subject_graph sg
+            JOIN authorization_relations r
+                ON r.resource_type = sg.subject_type
+                AND r.resource_id = sg.subject_id
+            WHERE sg.subject_type IN ('department', 'organization', 'group')
+            AND r.relation IN ('department_member', 'department_head')
+            AND r.is_active = 1
+            AND (r.expires_at IS NULL OR r.expires_at > ?5)
+            AND sg.depth < ?4
+            AND instr(sg.path, '|' || r.subject_type || ':' || r.subject_id || '|') = 0
+        )
+        SELECT DISTINCT subject_type, subject_id
+        FROM subject_graph
+        WHERE depth > 1
+    "#;
+
+    /// Get every group a subject belongs to, bounded by `?3` levels (`?4` is the current time)
+    pub const GET_SUBJECT_HIERARCHY: &str = r#"
+        WITH RECURSIVE hierarchy(resource_type, resource_id, depth, path) AS (
+            SELECT resource_type, resource_id, 1,
+                   '|' || ?1 || ':' || ?2 || '|' || resource_type || ':' || resource_id || '|'
+            FROM authorization_relations
+            WHERE subject_type = ?1
+            AND subject_id = ?2
+            AND relation IN ('department_member', 'department_head')
+            AND resource_type IN ('department', 'organization', 'group')
+            AND is_active = 1
+            AND (expires_at IS NULL OR expires_at > ?4)
+
+            UNION ALL
+
+            SELECT r.resource_type, r.resource_id, h.depth + 1,
+                   h.path || r.resource_type || ':' || r.resource_id || '|'
+            FROM hierarchy h
+            JOIN authorization_relations r
+                ON r.subject_type = h.resource_type
+                AND r.subject_id = h.resource_id
+            WHERE r.relation IN ('department_member', 'department_head')
+            AND r.resource_type IN ('department', 'organization', 'group')
+            AND r.is_active = 1
+            AND (r.expires_at IS NULL OR r.expires_at > ?4)
+            AND h.depth < ?3
+            AND instr(h.path, '|' || r.resource_type || ':' || r.resource_id || '|') = 0
+        )
+        SELECT DISTINCT resource_type, resource_id
+        FROM hierarchy
+    "#;
+
+    /// Deactivate expired relationships (`?1` is the current time)
+    pub const CLEANUP_EXPIRED_RELATIONSHIPS: &str = r#"
+        UPDATE authorization_relations
+        SET is_active = 0
+        WHERE expires_at IS NOT NULL
+        AND expires_at <= ?1
+        AND is_active = 1
+    "#;
+
+    /// Insert or update a policy by name
+    pub const UPSERT_POLICY: &str = r#"
+        INSERT INTO authorization_policies
+            (id, name, description, policy_type, conditions, effect, priority,
+             is_active, created_at, updated_at, metadata)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        ON CONFLICT (name) DO UPDATE SET
+            description = excluded.description,
+            policy_type = excluded.policy_type,
+            conditions = excluded.conditions,
+            effect = excluded.effect,
+            priority = excluded.priority,
+            is_active = excluded.is_active,
+            updated_at = excluded.updated_at,
+            metadata = excluded.metadata
+    "#;
+
+    /// Get a policy by ID
+    pub const GET_POLICY: &str = r#"
+        SELECT id, name, description, policy_type, conditions, effect, priority,
+               is_active, created_at, updated_at, metadata
+        FROM authorization_policies
+        WHERE id = ?1
+    "#;
+
+    /// Get all active policies
+    pub const GET_ACTIVE_POLICIES: &str = r#"
+        SELECT id, name, description, policy_type, conditions, effect, priority,
+               is_active, created_at, updated_at, metadata
+        FROM authorization_policies
+        WHERE is_active = 1
+        ORDER BY priority DESC
+    "#;
+
+    /// Get policies of a given type
+    pub const GET_POLICIES_BY_TYPE: &str = r#"
+        SELECT id, name, description, policy_type, conditions, effect, priority,
+               is_active, created_at, updated_at, metadata
+        FROM authorization_policies
+        WHERE policy_type = ?1
+        ORDER BY priority DESC
+    "#;
+
+    /// Activate or deactivate a policy
+    pub const UPDATE_POLICY_STATUS: &str = r#"
+        UPDATE authorization_policies
+        SET is_active = ?1, updated_at = ?2
+        WHERE id = ?3
+    "#;
+
+    /// Insert an audit entry
+    pub const INSERT_AUDIT_ENTRY: &str = r#"
+        INSERT INTO authorization_audit_log
+            (id, user_id, action, resource_type, resource_id, decision, reasons,
+             ip_address, user_agent, session_id, metadata, timestamp)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+    "#;
+
+    /// Usage statistics for a policy, matched against the JSON reasons array
+    pub const GET_POLICY_USAGE_STATS: &str = r#"
+        SELECT
+            COUNT(*),
+            COALESCE(SUM(CASE WHEN decision = 'allow' THEN 1 ELSE 0 END), 0),
+            COALESCE(SUM(CASE WHEN decision = 'deny' THEN 1 ELSE 0 END), 0),
+            MAX(timestamp)
+        FROM authorization_audit_log
+        WHERE EXISTS (SELECT 1 FROM json_each(reasons) WHERE json_each.value = ?1)
+    "#;
+
+    /// Read changes recorded after a revision
+    pub const GET_CHANGES_SINCE: &str = r#"
+        SELECT revision, operation, resource_type, resource_id, relation,
+               subject_type, subject_id, changed_by, changed_at
+        FROM authorization_changelog
+        WHERE revision > ?1
+        ORDER BY revision ASC
+        LIMIT ?2
+    "#;
+
+    /// Get the latest recorded revision
+    pub const GET_CURRENT_REVISION: &str = r#"
+        SELECT COALESCE(MAX(revision), 0) FROM authorization_changelog
+    "#;
+}
@@ -0,0 +1,123 @@
+// src/modules/authorization/changelog.rs
+//! Relationship changelog and Watch API types
+//!
+//! Every relationship tuple write is recorded in `authorization_changelog` with a
+//! monotonically increasing revision. Consumers replay changes after the last
+//! zookie they have seen to keep external caches consistent with the store.
+
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::relations::RelationshipTuple;
+use super::error::{AuthError, AuthResult};
+
+/// Prefix embedded in encoded zookies so foreign tokens are rejected
+const ZOOKIE_PREFIX: &str = "hims-rev";
+
+/// Opaque token identifying a revision of the authorization store
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Zookie(String);
+
+impl Zookie {
+    /// Create a zookie for a changelog revision
+    pub fn from_revision(revision: i64) -> Self {
+        let raw = format!("{}:{}", ZOOKIE_PREFIX, revision);
+        Self(general_purpose::URL_SAFE_NO_PAD.encode(raw))
+    }
+
+    /// Parse a zookie received from a client
+    pub fn parse(token: &str) -> AuthResult<Self> {
+        let zookie = Self(token.to_string());
+        zookie.revision()?;
+        Ok(zookie)
+    }
+
+    /// Decode the revision this zookie refers to
+    pub fn revision(&self) -> AuthResult<i64> {
+        let invalid = || AuthError::Validation(format!("Invalid zookie: {}", self.0));
+
+        let bytes = general_purpose::URL_SAFE_NO_PAD.decode(&self.0).map_err(|_| invalid())?;
+        let raw = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (prefix, revision) = raw.split_once(':').ok_or_else(invalid)?;
+        if prefix != ZOOKIE_PREFIX {
+            return Err(invalid());
+        }
+
+        revision.parse().map_err(|_| invalid())
+    }
+
+    /// Get the encoded token
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Zookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Kind of change applied to a relationship tuple
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeOperation {
+    Added,
+    Removed,
+}
+
+impl Display for ChangeOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeOperation::Added => write!(f, "added"),
+            ChangeOperation::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+impl std::str::FromStr for ChangeOperation {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "added" => Ok(ChangeOperation::Added),
+            "removed" => Ok(ChangeOperation::Removed),
+            _ => Err(AuthError::Validation(format!("Unknown change operation: {}", s))),
+        }
+    }
+}
+
+/// A single entry from the relationship changelog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationshipChange {
+    /// Changelog revision
+    pub revision: i64,
+    /// Token consumers persist to resume watching after this change
+    pub zookie: Zookie,
+    /// Whether the tuple was added or removed
+    pub operation: ChangeOperation,
+    /// The affected relationship tuple
+    pub tuple: RelationshipTuple,
+    /// When the change was recorded
+    pub changed_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zookie_round_trip() {
+        let zookie = Zookie::from_revision(42);
+        assert_eq!(zookie.revision().unwrap(), 42);
+        assert_eq!(Zookie::parse(zookie.as_str()).unwrap(), zookie);
+    }
+
+    #[test]
+    fn test_zookie_rejects_foreign_tokens() {
+        assert!(Zookie::parse("not-a-zookie").is_err());
+        let foreign = general_purpose::URL_SAFE_NO_PAD.encode("other:42");
+        assert!(Zookie::parse(&foreign).is_err());
+    }
+}
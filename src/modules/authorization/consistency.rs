@@ -0,0 +1,30 @@
+// src/modules/authorization/consistency.rs
+//! Consistency requirements for authorization checks
+//!
+//! Relationship writes return a [`Zookie`] for the changelog revision they were
+//! committed at. Callers that need read-after-write semantics (for example,
+//! granting a care team member access and immediately opening the chart on
+//! another instance) pass that zookie back with the check.
+
+use serde::{Deserialize, Serialize};
+
+use super::changelog::Zookie;
+
+/// How fresh the data used to evaluate a check must be
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Consistency {
+    /// Use cached relationships when available
+    #[default]
+    MinimizeLatency,
+    /// Evaluate against a store that has applied at least this revision
+    AtLeastAsFresh(Zookie),
+    /// Bypass caches and evaluate against the latest revision
+    FullyConsistent,
+}
+
+impl Consistency {
+    /// Whether cached relationship data may be used
+    pub fn allows_cache(&self) -> bool {
+        matches!(self, Consistency::MinimizeLatency)
+    }
+}
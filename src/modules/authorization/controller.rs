@@ -0,0 +1,108 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, AuthorizationEngine, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::get_user_session_context;
+
+/// Authorization controller answering access checks over REST, as the gRPC
+/// Authorization service does, for clients without gRPC
+pub struct AuthorizationController {
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckRequest {
+    /// e.g. "read", "prescribe", "view_results"
+    pub action: String,
+    /// type:id, e.g. "patient:0d5c7a0e-..."
+    pub resource: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckResponse {
+    pub allowed: bool,
+    /// allow, deny, require_approval, require_mfa, allow_with_restrictions,
+    /// emergency_access or break_glass_access
+    pub decision: String,
+    pub reasons: Vec<String>,
+    pub requirements: Vec<String>,
+    pub restrictions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+impl AuthorizationController {
+    /// Create new controller with the shared authorization engine
+    pub fn new(authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        Self { authorization_engine }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/check", Self::check, "Check whether the caller may take an action on a resource")
+            .with_state(self.authorization_engine.clone())
+    }
+
+    /// The engine's decision on whether the caller may take the action on
+    /// the resource, for the purpose of use the request declares
+    pub async fn check(
+        State(authorization_engine): State<Arc<HimsAuthorizationEngine>>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Json(check): Json<CheckRequest>,
+    ) -> Result<Json<CheckResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let action: Action = check.action.parse().map_err(|message| Self::bad_request("action", message))?;
+        let resource: Resource = check.resource.parse().map_err(|message| Self::bad_request("resource", message))?;
+
+        let context = get_user_session_context(&auth, &headers).await.map_err(|e| {
+            tracing::error!("Failed to get user session context: {}", e);
+            Self::internal_error("Internal server error", "Failed to establish session context")
+        })?;
+        let authorizer = FieldAuthorizer::for_request(authorization_engine.clone(), &auth, context);
+        let response = authorization_engine.check(authorizer.request(action, resource)).await.map_err(|e| {
+            tracing::error!("Authorization check failed: {}", e);
+            Self::internal_error("Authorization error", "Failed to check authorization")
+        })?;
+
+        Ok(Json(CheckResponse {
+            allowed: FieldAuthorizer::permits(&response),
+            decision: response.decision.to_string(),
+            reasons: response.reasons,
+            requirements: response.requirements,
+            restrictions: response.restrictions,
+        }))
+    }
+
+    fn bad_request(field: &str, message: String) -> (StatusCode, Json<ErrorResponse>) {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: field.to_string(),
+                message,
+            }),
+        )
+    }
+
+    fn internal_error(error: &str, message: &str) -> (StatusCode, Json<ErrorResponse>) {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message: message.to_string(),
+            }),
+        )
+    }
+}
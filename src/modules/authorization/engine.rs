@@ -6,19 +6,31 @@
 
 use async_trait::async_trait;
 use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
-use anyhow::Result;
 use uuid::Uuid;
 use tokio::time::Duration;
+use chrono::Utc;
 
 use super::relations::{Subject, Resource, Action, HealthcareRelation, RelationshipTuple};
-use super::policies::{PolicyEngine, PolicyDecision, PolicyEffect, HimsPolicyEngine};
-use super::healthcare_context::RequestContext;
-use super::storage::{AuthorizationStorage, RelationStorage, PostgresAuthorizationStorage};
-use super::audit::{AuditManager, AuditEntry, AccessDecision, AuthorizationAudit};
+use super::policies::{HimsPolicyEngine, PolicyEngine, PolicyDecision, PolicyEffect};
+use super::healthcare_context::{ConfidentialityLabel, PurposeOfUse, RequestContext, UrgencyLevel};
+use super::storage::{
+    create_storage, AuthorizationBackend,
+    PostgresAuthorizationStorage,
+};
+use super::changelog::{RelationshipChange, Zookie};
+use super::consistency::Consistency;
+use super::audit::{AuditConfig, AuditManager, AccessDecision};
 use super::error::{AuthError, AuthResult};
-use super::{AuthorizationConfig, AuthorizationRequest};
+use super::external_policy::ExternalPolicyEngine;
+use super::{AuthorizationConfig, AuthorizationRequest, PolicyBackend, SessionContext};
+use crate::database::tenant::current_tenant_id;
+use crate::modules::emergency::TriageUrgency;
+use crate::modules::part2::Part2Consents;
+use crate::modules::security_label::RecordSecurityLabels;
 
 /// Response from authorization evaluation
 #[derive(Debug, Clone)]
@@ -49,6 +61,10 @@ pub trait AuthorizationEngine: Send + Sync {
     /// Evaluate an authorization request
     async fn check(&self, request: AuthorizationRequest) -> AuthResult<AuthorizationResponse>;
     
+    /// Evaluate many authorization requests in one call, sharing policy
+    /// evaluation and relationship lookups. Responses are returned in request order.
+    async fn check_batch(&self, requests: Vec<AuthorizationRequest>) -> AuthResult<Vec<AuthorizationResponse>>;
+    
     /// Expand relationships to find all subjects with a given relation to a resource
     async fn expand(
         &self,
@@ -64,11 +80,11 @@ pub trait AuthorizationEngine: Send + Sync {
         resource_type: String,
     ) -> AuthResult<Vec<Resource>>;
     
-    /// Add a relationship
-    async fn add_relationship(&self, tuple: RelationshipTuple) -> AuthResult<()>;
+    /// Add a relationship, returning a zookie for read-after-write checks
+    async fn add_relationship(&self, tuple: RelationshipTuple) -> AuthResult<Zookie>;
     
-    /// Remove a relationship
-    async fn remove_relationship(&self, tuple: RelationshipTuple) -> AuthResult<()>;
+    /// Remove a relationship, returning a zookie for read-after-write checks
+    async fn remove_relationship(&self, tuple: RelationshipTuple) -> AuthResult<Zookie>;
     
     /// Check if a specific relationship exists
     async fn has_relationship(
@@ -77,22 +93,126 @@ pub trait AuthorizationEngine: Send + Sync {
         relation: HealthcareRelation,
         subject: Subject,
     ) -> AuthResult<bool>;
+    
+    /// Read relationship changes recorded after a zookie (from the beginning if `None`)
+    async fn read_changes(&self, since: Option<Zookie>, limit: i64) -> AuthResult<Vec<RelationshipChange>>;
+    
+    /// Stream relationship changes recorded after a zookie. Starts from the
+    /// current revision if `None`. The stream ends when the receiver is dropped.
+    async fn watch(&self, since: Option<Zookie>) -> AuthResult<tokio::sync::mpsc::Receiver<RelationshipChange>>;
+}
+
+/// Clinical urgency of a patient known to the system rather than asserted
+/// by the request, e.g. from emergency department triage
+#[async_trait]
+pub trait UrgencySource: Send + Sync {
+    /// Urgency of the patient's current care, or `None` if nothing is known
+    async fn patient_urgency(&self, patient_id: Uuid) -> AuthResult<Option<UrgencyLevel>>;
+    
+    /// Urgency of each of the patients anything is known of. Sources that
+    /// can should look them all up at once; this asks for one at a time.
+    async fn patient_urgency_batch(&self, patient_ids: &[Uuid]) -> AuthResult<HashMap<Uuid, UrgencyLevel>> {
+        let mut urgencies = HashMap::new();
+        for patient_id in patient_ids {
+            if let Some(urgency_level) = self.patient_urgency(*patient_id).await? {
+                urgencies.insert(*patient_id, urgency_level);
+            }
+        }
+        Ok(urgencies)
+    }
+}
+
+/// Confidentiality labels recorded on resources, e.g. in FHIR `meta.security`
+#[async_trait]
+pub trait SecurityLabelSource: Send + Sync {
+    /// Labels of the resource, including those of the patient it is about
+    async fn security_labels(
+        &self,
+        resource: &Resource,
+        patient_id: Option<Uuid>,
+    ) -> AuthResult<Vec<ConfidentialityLabel>>;
+    
+    /// Labels of many resources, one list per resource in order. Sources
+    /// that can should look them all up at once; this asks for one at a time.
+    async fn security_labels_batch(
+        &self,
+        resources: &[(Resource, Option<Uuid>)],
+    ) -> AuthResult<Vec<Vec<ConfidentialityLabel>>> {
+        let mut labels = Vec::with_capacity(resources.len());
+        for (resource, patient_id) in resources {
+            labels.push(self.security_labels(resource, *patient_id).await?);
+        }
+        Ok(labels)
+    }
+}
+
+/// Consents patients gave to disclosures of their records
+#[async_trait]
+pub trait ConsentSource: Send + Sync {
+    /// Whether a 42 CFR Part 2 consent lets the patient's substance use
+    /// records reach `recipient` for `purpose`
+    async fn part2_consent(&self, patient_id: Uuid, recipient: &Subject, purpose: PurposeOfUse) -> AuthResult<bool>;
+    
+    /// Whether consents cover each of many disclosures, in order. Sources
+    /// that can should look them all up at once; this asks for one at a time.
+    async fn part2_consent_batch(&self, disclosures: &[(Uuid, Subject, PurposeOfUse)]) -> AuthResult<Vec<bool>> {
+        let mut consents = Vec::with_capacity(disclosures.len());
+        for (patient_id, recipient, purpose) in disclosures {
+            consents.push(self.part2_consent(*patient_id, recipient, *purpose).await?);
+        }
+        Ok(consents)
+    }
+}
+
+/// Process-wide relation cache counters across all engine instances
+static RELATION_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static RELATION_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Relation cache (hits, misses) since startup
+pub fn relation_cache_stats() -> (u64, u64) {
+    (
+        RELATION_CACHE_HITS.load(Ordering::Relaxed),
+        RELATION_CACHE_MISSES.load(Ordering::Relaxed),
+    )
+}
+
+/// Subjects holding each cached relation, by cache key, with when they were looked up
+type RelationCache = Arc<tokio::sync::RwLock<HashMap<String, (Vec<Subject>, Instant)>>>;
+
+/// Distinct keys in order of first appearance, and the position of each
+/// key among them, so a batch looks each key up once
+fn distinct_keys<K: Clone + Eq + Hash>(keys: impl IntoIterator<Item = K>) -> (Vec<K>, Vec<usize>) {
+    let mut distinct = Vec::new();
+    let mut positions = HashMap::new();
+    let slots = keys
+        .into_iter()
+        .map(|key| {
+            *positions.entry(key.clone()).or_insert_with(|| {
+                distinct.push(key);
+                distinct.len() - 1
+            })
+        })
+        .collect();
+    (distinct, slots)
 }
 
 /// Main implementation of the healthcare authorization engine
 pub struct HimsAuthorizationEngine {
-    storage: Arc<PostgresAuthorizationStorage>,
-    policy_engine: Arc<HimsPolicyEngine>,
+    storage: Arc<dyn AuthorizationBackend>,
+    policy_engine: Arc<dyn PolicyEngine>,
     audit_manager: Arc<AuditManager>,
     config: AuthorizationConfig,
-    relation_cache: Arc<tokio::sync::RwLock<HashMap<String, (Vec<Subject>, Instant)>>>,
+    relation_cache: RelationCache,
+    urgency_source: Option<Arc<dyn UrgencySource>>,
+    label_source: Option<Arc<dyn SecurityLabelSource>>,
+    consent_source: Option<Arc<dyn ConsentSource>>,
 }
 
 impl HimsAuthorizationEngine {
     /// Create a new authorization engine
     pub fn new(
-        storage: Arc<PostgresAuthorizationStorage>,
-        policy_engine: Arc<HimsPolicyEngine>,
+        storage: Arc<dyn AuthorizationBackend>,
+        policy_engine: Arc<dyn PolicyEngine>,
         audit_manager: Arc<AuditManager>,
         config: AuthorizationConfig,
     ) -> Self {
@@ -102,9 +222,83 @@ impl HimsAuthorizationEngine {
             audit_manager,
             config,
             relation_cache: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            urgency_source: None,
+            label_source: None,
+            consent_source: None,
         }
     }
     
+    /// Raise the urgency of requests about a patient to what `source` knows
+    /// of the patient
+    pub fn with_urgency_source(mut self, source: Arc<dyn UrgencySource>) -> Self {
+        self.urgency_source = Some(source);
+        self
+    }
+    
+    /// Evaluate policies against the confidentiality labels `source` records
+    /// on the requested resources
+    pub fn with_label_source(mut self, source: Arc<dyn SecurityLabelSource>) -> Self {
+        self.label_source = Some(source);
+        self
+    }
+    
+    /// Look up the consents covering requests for substance use records in
+    /// `source`
+    pub fn with_consent_source(mut self, source: Arc<dyn ConsentSource>) -> Self {
+        self.consent_source = Some(source);
+        self
+    }
+
+    /// Engine on the shared PostgreSQL storage with the built-in policies
+    /// and default configuration, for callers that cannot await
+    /// `from_config`
+    pub fn postgres(pool: sqlx::PgPool) -> Self {
+        Self::new(
+            Arc::new(PostgresAuthorizationStorage::new(pool.clone())),
+            Arc::new(HimsPolicyEngine::new()),
+            Arc::new(AuditManager::new(AuditConfig::default())),
+            AuthorizationConfig::default(),
+        )
+        .with_postgres_sources(pool)
+    }
+    
+    /// Engine on the storage backend and policy engine `config` selects.
+    /// `pool` is the server's PostgreSQL database, which the PostgreSQL
+    /// backend requires. Without one, as on the desktop, urgency, labels and
+    /// consents are not looked up.
+    pub async fn from_config(config: AuthorizationConfig, pool: Option<sqlx::PgPool>) -> AuthResult<Self> {
+        let storage = create_storage(&config, pool.clone()).await?;
+        let policy_engine: Arc<dyn PolicyEngine> = match &config.policy_backend {
+            PolicyBackend::Builtin => Arc::new(HimsPolicyEngine::new()),
+            PolicyBackend::External(external) => Arc::new(
+                ExternalPolicyEngine::new(external.clone()).map_err(|e| AuthError::Configuration(e.to_string()))?,
+            ),
+        };
+        let audit_config = AuditConfig {
+            enabled: config.enable_audit,
+            ..AuditConfig::default()
+        };
+        let engine = Self::new(
+            storage,
+            policy_engine,
+            Arc::new(AuditManager::new(audit_config)),
+            config,
+        );
+        Ok(match pool {
+            Some(pool) => engine.with_postgres_sources(pool),
+            None => engine,
+        })
+    }
+    
+    /// Take the urgency of patients in the emergency department from their
+    /// triage, the labels of records from their `meta.security` and Part 2
+    /// consents from the registry
+    fn with_postgres_sources(self, pool: sqlx::PgPool) -> Self {
+        self.with_urgency_source(Arc::new(TriageUrgency::new(pool.clone())))
+            .with_label_source(Arc::new(RecordSecurityLabels::new(pool.clone())))
+            .with_consent_source(Arc::new(Part2Consents::new(pool)))
+    }
+    
     /// Validate the request context
     async fn validate_context(&self, context: &RequestContext) -> AuthResult<()> {
         context.validate()
@@ -112,6 +306,112 @@ impl HimsAuthorizationEngine {
         Ok(())
     }
     
+    /// Add what the system knows of the requests' patients and resources to
+    /// their contexts, with one lookup per source however many requests
+    /// there are. Runs after context validation.
+    async fn enrich(&self, requests: &mut [AuthorizationRequest]) -> AuthResult<()> {
+        self.apply_patient_urgency(requests).await;
+        self.apply_security_labels(requests).await?;
+        self.apply_part2_consent(requests).await
+    }
+    
+    /// Raise the requests' urgency to what the urgency source knows of the
+    /// patients they are about. An urgency the system derived needs no
+    /// emergency declaration, unlike one a client asserts. A failed lookup
+    /// leaves the requests' own urgency.
+    async fn apply_patient_urgency(&self, requests: &mut [AuthorizationRequest]) {
+        let Some(source) = &self.urgency_source else {
+            return;
+        };
+        let (patient_ids, _) = distinct_keys(requests.iter().filter_map(Self::patient_of));
+        if patient_ids.is_empty() {
+            return;
+        }
+        match source.patient_urgency_batch(&patient_ids).await {
+            Ok(urgencies) => {
+                for request in requests.iter_mut() {
+                    let Some(patient_id) = Self::patient_of(request) else {
+                        continue;
+                    };
+                    if let Some(urgency_level) = urgencies.get(&patient_id) {
+                        request.context.raise_urgency(patient_id, urgency_level.clone(), "emergency department triage");
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Failed to look up urgency of patients {:?}: {}", patient_ids, e),
+        }
+    }
+    
+    /// Patient the request is about, from its clinical context or resource
+    fn patient_of(request: &AuthorizationRequest) -> Option<Uuid> {
+        match (&request.context.clinical, &request.resource) {
+            (Some(clinical), _) if clinical.patient_id.is_some() => clinical.patient_id,
+            (_, Resource::Patient(patient_id)) => Some(*patient_id),
+            _ => None,
+        }
+    }
+    
+    /// Attach the labels recorded on each resource to its request's context.
+    /// Unlike urgency, a failed lookup fails the requests: evaluating without
+    /// the labels would expose sensitive records.
+    async fn apply_security_labels(&self, requests: &mut [AuthorizationRequest]) -> AuthResult<()> {
+        let Some(source) = &self.label_source else {
+            return Ok(());
+        };
+        if requests.is_empty() {
+            return Ok(());
+        }
+        let (resources, slots) = distinct_keys(
+            requests.iter().map(|request| (request.resource.clone(), Self::patient_of(request))),
+        );
+        let labels = source.security_labels_batch(&resources).await?;
+        if labels.len() != resources.len() {
+            return Err(AuthError::Engine(format!(
+                "Label source returned labels of {} resources for {}",
+                labels.len(),
+                resources.len()
+            )));
+        }
+        for (request, slot) in requests.iter_mut().zip(slots) {
+            request.context = std::mem::take(&mut request.context).with_security_labels(labels[slot].clone());
+        }
+        Ok(())
+    }
+    
+    /// Record on each request whether a Part 2 consent covers it, for
+    /// requests about substance use records of a known patient with a
+    /// declared purpose. Runs after the labels are applied.
+    async fn apply_part2_consent(&self, requests: &mut [AuthorizationRequest]) -> AuthResult<()> {
+        let Some(source) = &self.consent_source else {
+            return Ok(());
+        };
+        let (indices, disclosures): (Vec<usize>, Vec<(Uuid, Subject, PurposeOfUse)>) = requests
+            .iter()
+            .enumerate()
+            .filter(|(_, request)| request.context.security_labels.contains(&ConfidentialityLabel::SubstanceAbuse))
+            .filter_map(|(index, request)| {
+                let (patient_id, purpose) = (Self::patient_of(request)?, request.context.purpose()?);
+                Some((index, (patient_id, request.subject.clone(), purpose)))
+            })
+            .unzip();
+        if disclosures.is_empty() {
+            return Ok(());
+        }
+        let (disclosures, slots) = distinct_keys(disclosures);
+        let consents = source.part2_consent_batch(&disclosures).await?;
+        if consents.len() != disclosures.len() {
+            return Err(AuthError::Engine(format!(
+                "Consent source returned {} consents for {} disclosures",
+                consents.len(),
+                disclosures.len()
+            )));
+        }
+        for (index, slot) in indices.into_iter().zip(slots) {
+            requests[index].context.part2_consent = consents[slot];
+        }
+        Ok(())
+    }
+    
     /// Check emergency access scenarios
     async fn check_emergency_access(&self, request: &AuthorizationRequest) -> AuthResult<bool> {
         if !self.config.enable_emergency_access {
@@ -157,6 +457,7 @@ impl HimsAuthorizationEngine {
         relation: &'a HealthcareRelation,
         visited: &'a mut HashSet<String>,
         depth: u8,
+        use_cache: bool,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = AuthResult<bool>> + Send + 'a>> {
         Box::pin(async move {
         if depth >= self.config.max_relation_depth {
@@ -171,15 +472,17 @@ impl HimsAuthorizationEngine {
         }
         visited.insert(cache_key.clone());
         
-        // Check cache first if enabled
-        if self.config.enable_caching {
+        // Check cache first if enabled and the request tolerates cached data
+        if self.config.enable_caching && use_cache {
             let cache = self.relation_cache.read().await;
-            if let Some((subjects, cached_at)) = cache.get(&format!("{}#{}", resource, relation)) {
+            if let Some((subjects, cached_at)) = cache.get(&Self::cache_key(resource, relation)) {
                 let cache_age = cached_at.elapsed();
                 if cache_age < Duration::from_secs(self.config.cache_ttl_seconds) {
+                    RELATION_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
                     return Ok(subjects.contains(subject));
                 }
             }
+            RELATION_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
         }
         
         // Check direct relationship
@@ -187,9 +490,16 @@ impl HimsAuthorizationEngine {
             return Ok(true);
         }
         
+        // Check relationships inherited through department/organization/group membership
+        let remaining_depth = self.config.max_relation_depth.saturating_sub(depth);
+        let inherited = self.storage.find_inherited_relationships(resource, relation, remaining_depth).await?;
+        if inherited.contains(subject) {
+            return Ok(true);
+        }
+        
         // Check inherited relationships
         if let Some(parent_relation) = self.get_parent_relation(relation) {
-            if self.resolve_relationships(resource, subject, &parent_relation, visited, depth + 1).await? {
+            if self.resolve_relationships(resource, subject, &parent_relation, visited, depth + 1, use_cache).await? {
                 return Ok(true);
             }
         }
@@ -229,13 +539,28 @@ impl HimsAuthorizationEngine {
     
     /// Check department membership
     async fn check_department_membership(&self, resource: &Resource, user_id: Uuid) -> AuthResult<bool> {
-        // This would check if the user is a member of the department associated with the resource
-        // For now, return false to avoid complex implementation
-        Ok(false)
+        let departments: Vec<Subject> = self.storage
+            .get_subject_hierarchy(&Subject::User(user_id), self.config.max_relation_depth)
+            .await?
+            .into_iter()
+            .filter(|subject| matches!(subject, Subject::Department(_)))
+            .collect();
+        if departments.is_empty() {
+            return Ok(false);
+        }
+        
+        // The resource belongs to a department only through the department
+        // member relation held by the department itself. Other relations, or
+        // any held by an organization or group, say nothing of which
+        // department the resource belongs to.
+        let relationships = self.storage.get_relationships_for_resource(resource).await?;
+        Ok(relationships.iter().any(|tuple| {
+            tuple.relation == HealthcareRelation::DepartmentMember && departments.contains(&tuple.subject)
+        }))
     }
     
     /// Check care team membership
-    async fn check_care_team_membership(&self, resource: &Resource, user_id: Uuid) -> AuthResult<bool> {
+    async fn check_care_team_membership(&self, _resource: &Resource, _user_id: Uuid) -> AuthResult<bool> {
         // This would check if the user is part of the patient's care team
         // For now, return false to avoid complex implementation
         Ok(false)
@@ -254,6 +579,9 @@ impl HimsAuthorizationEngine {
                 HealthcareRelation::PrimaryPhysician,
                 HealthcareRelation::TreatingPhysician,
             ]),
+            (Action::Delete, Resource::Patient(_)) => Ok(vec![
+                HealthcareRelation::PrimaryPhysician,
+            ]),
             (Action::Prescribe, Resource::Patient(_)) => Ok(vec![
                 HealthcareRelation::PrimaryPhysician,
                 HealthcareRelation::ConsultingPhysician,
@@ -306,13 +634,281 @@ impl HimsAuthorizationEngine {
         Ok(())
     }
     
+    /// Relation granting a request whose policy decision defers to
+    /// relationship checks: the first of the required relations, or of those
+    /// the policy limits access to, that the subject holds on the resource.
+    /// Both `check` and `check_batch` decide here. Without `known`, each
+    /// relation is resolved against the store. A batch passes the tuples it
+    /// found instead, keyed by the requesting subject, and they settle the
+    /// request alone: the batch looked for every relation and the relations
+    /// it inherits from, held by the subject or any department,
+    /// organization or group it belongs to. That covers what resolution
+    /// finds through inherited relationships and department membership.
+    async fn relation_match(
+        &self,
+        request: &AuthorizationRequest,
+        policy_decision: &PolicyDecision,
+        known: Option<&HashSet<(Resource, HealthcareRelation, Subject)>>,
+    ) -> AuthResult<Option<HealthcareRelation>> {
+        let relations = match &policy_decision.decision {
+            PolicyEffect::AuditOnly => self.get_required_relations(&request.action, &request.resource).await?,
+            PolicyEffect::RequireRelations(relations) => relations.clone(),
+            _ => return Ok(None),
+        };
+        
+        for relation in relations {
+            if let Some(known) = known {
+                let settled = self.with_parent_relations(vec![relation.clone()]).into_iter().any(|inherited| {
+                    known.contains(&(request.resource.clone(), inherited, request.subject.clone()))
+                });
+                if settled {
+                    return Ok(Some(relation));
+                }
+                continue;
+            }
+            
+            let mut visited = HashSet::new();
+            if self.resolve_relationships(
+                &request.resource,
+                &request.subject,
+                &relation,
+                &mut visited,
+                0,
+                request.consistency.allows_cache(),
+            ).await? {
+                return Ok(Some(relation));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// Relations plus the relations they inherit from
+    fn with_parent_relations(&self, required: Vec<HealthcareRelation>) -> Vec<HealthcareRelation> {
+        let mut relations = Vec::new();
+        for relation in required {
+            let mut current = Some(relation);
+            while let Some(relation) = current {
+                current = self.get_parent_relation(&relation);
+                if !relations.contains(&relation) {
+                    relations.push(relation);
+                }
+            }
+        }
+        relations
+    }
+    
+    /// Wait until the store has applied the revision required by the request
+    async fn ensure_consistency(&self, consistency: &Consistency) -> AuthResult<()> {
+        match consistency {
+            Consistency::AtLeastAsFresh(zookie) => self.wait_for_revision(zookie.revision()?).await,
+            Consistency::MinimizeLatency | Consistency::FullyConsistent => Ok(()),
+        }
+    }
+    
+    /// Wait until the store has applied `required_revision`
+    async fn wait_for_revision(&self, required_revision: i64) -> AuthResult<()> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.consistency_wait_ms);
+        loop {
+            let current_revision = self.storage.current_revision().await?;
+            if current_revision >= required_revision {
+                return Ok(());
+            }
+            
+            if Instant::now() >= deadline {
+                return Err(AuthError::Consistency(format!(
+                    "store is at revision {} but revision {} was requested",
+                    current_revision, required_revision
+                )));
+            }
+            
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+    
+    /// Get a zookie for the latest revision of the store
+    async fn current_zookie(&self) -> AuthResult<Zookie> {
+        let revision = self.storage.current_revision().await?;
+        Ok(Zookie::from_revision(revision))
+    }
+    
+    /// Response granted through emergency access
+    fn emergency_response() -> AuthorizationResponse {
+        AuthorizationResponse {
+            allowed: true,
+            decision: AccessDecision::EmergencyAccess,
+            reasons: vec!["Emergency access granted".to_string()],
+            requirements: Vec::new(),
+            time_limit: None,
+            restrictions: Vec::new(),
+            confidence: 1.0,
+            evaluation_time_ms: 0,
+            request_id: None,
+        }
+    }
+    
+    /// Whether the session completed MFA recently enough for step-up actions
+    fn has_fresh_mfa(&self, session: &SessionContext) -> bool {
+        match (session.mfa_verified, session.mfa_verified_at) {
+            (true, Some(verified_at)) => {
+                (Utc::now() - verified_at).num_seconds() <= self.config.step_up_max_age_secs
+            }
+            _ => false,
+        }
+    }
+    
+    /// Sensitive actions require MFA re-verification within the step-up window
+    fn step_up_response(&self, request: &AuthorizationRequest) -> Option<AuthorizationResponse> {
+        if !self.config.step_up_actions.contains(&request.action) || self.has_fresh_mfa(&request.session) {
+            return None;
+        }
+        
+        Some(AuthorizationResponse {
+            allowed: false,
+            decision: AccessDecision::RequireMFA,
+            reasons: vec![format!("Action {} requires step-up authentication", request.action)],
+            requirements: vec!["Multi-factor re-verification required".to_string()],
+            time_limit: None,
+            restrictions: Vec::new(),
+            confidence: 1.0,
+            evaluation_time_ms: 0,
+            request_id: None,
+        })
+    }
+    
+    /// Policies see the higher of the request and session risk scores
+    fn apply_session_risk(request: &mut AuthorizationRequest) {
+        request.context.risk_score = request.context.risk_score.max(request.session.risk_score);
+    }
+    
+    /// A second-factor requirement is met by an MFA-verified session, leaving
+    /// the decision to relationship checks
+    fn apply_mfa(policy_decision: PolicyDecision, session: &SessionContext) -> PolicyDecision {
+        if session.mfa_verified && matches!(policy_decision.decision, PolicyEffect::RequireSecondFactor) {
+            PolicyDecision {
+                decision: PolicyEffect::AuditOnly,
+                ..policy_decision
+            }
+        } else {
+            policy_decision
+        }
+    }
+    
+    /// Build a response from a policy decision. `relation_match` is the relation
+    /// that granted access when the policy effect defers to relationship checks.
+    fn response_from_policy(
+        policy_decision: PolicyDecision,
+        relation_match: Option<HealthcareRelation>,
+    ) -> AuthorizationResponse {
+        let mut reasons = Vec::new();
+        let mut requirements = Vec::new();
+        let mut restrictions = Vec::new();
+        let mut time_limit = None;
+        let decision;
+        let confidence;
+        
+        match policy_decision.decision {
+            PolicyEffect::Allow => {
+                decision = AccessDecision::Allow;
+                reasons.extend(policy_decision.reasons);
+                confidence = policy_decision.confidence;
+            },
+            PolicyEffect::Deny => {
+                decision = AccessDecision::Deny;
+                reasons.extend(policy_decision.reasons);
+                confidence = policy_decision.confidence;
+            },
+            PolicyEffect::RequireApproval => {
+                decision = AccessDecision::RequireApproval;
+                requirements.extend(policy_decision.requirements);
+                confidence = policy_decision.confidence;
+            },
+            PolicyEffect::RequireSecondFactor => {
+                decision = AccessDecision::RequireMFA;
+                requirements.push("Multi-factor authentication required".to_string());
+                confidence = policy_decision.confidence;
+            },
+            PolicyEffect::AuditOnly => {
+                if let Some(relation) = relation_match {
+                    decision = AccessDecision::Allow;
+                    reasons.push(format!("Access granted via {} relationship", relation));
+                    confidence = 0.8;
+                } else {
+                    decision = AccessDecision::Deny;
+                    reasons.push("No valid relationship found".to_string());
+                    confidence = 0.9;
+                }
+            },
+            PolicyEffect::RequireRelations(relations) => {
+                reasons.extend(policy_decision.reasons);
+                if let Some(relation) = relation_match {
+                    decision = AccessDecision::Allow;
+                    reasons.push(format!("Labelled record accessed via {} relationship", relation));
+                    confidence = 0.8;
+                } else {
+                    let relations: Vec<String> = relations.iter().map(|r| r.to_string()).collect();
+                    decision = AccessDecision::Deny;
+                    reasons.push(format!(
+                        "Record is labelled sensitive; requires a {} relationship or break-glass access",
+                        relations.join(", ")
+                    ));
+                    confidence = 0.9;
+                }
+            },
+            PolicyEffect::TimeLimit(seconds) => {
+                time_limit = Some(Duration::from_secs(seconds));
+                decision = AccessDecision::AllowWithRestrictions;
+                restrictions.push(format!("Time limit: {} seconds", seconds));
+                confidence = policy_decision.confidence;
+            },
+            PolicyEffect::Restrict(restriction_list) => {
+                decision = AccessDecision::AllowWithRestrictions;
+                restrictions.extend(restriction_list);
+                confidence = policy_decision.confidence;
+            },
+            PolicyEffect::Conditional(_) => {
+                // Handle conditional policies based on additional requirements
+                requirements.extend(policy_decision.requirements);
+                if requirements.is_empty() {
+                    decision = AccessDecision::Allow;
+                } else {
+                    decision = AccessDecision::RequireApproval;
+                }
+                confidence = policy_decision.confidence;
+            },
+        }
+        
+        AuthorizationResponse {
+            allowed: matches!(decision, AccessDecision::Allow | AccessDecision::EmergencyAccess | AccessDecision::AllowWithRestrictions),
+            decision,
+            reasons,
+            requirements,
+            time_limit,
+            restrictions,
+            confidence,
+            evaluation_time_ms: 0,
+            request_id: None,
+        }
+    }
+    
+    /// Relationship cache key. Tuples are tenant-scoped, so the tenant is part of the key.
+    fn cache_key(resource: &Resource, relation: &HealthcareRelation) -> String {
+        format!("{}#{}#{}", current_tenant_id(), resource, relation)
+    }
+
+    /// Drop cached subjects for the resource/relation a write touched
+    async fn invalidate_cache(&self, tuple: &RelationshipTuple) {
+        let cache_key = Self::cache_key(&tuple.object, &tuple.relation);
+        self.relation_cache.write().await.remove(&cache_key);
+    }
+    
     /// Update relationship cache
     async fn update_cache(&self, resource: &Resource, relation: &HealthcareRelation, subjects: Vec<Subject>) {
         if !self.config.enable_caching {
             return;
         }
         
-        let cache_key = format!("{}#{}", resource, relation);
+        let cache_key = Self::cache_key(resource, relation);
         let mut cache = self.relation_cache.write().await;
         
         // Clean up cache if it's getting too large
@@ -326,23 +922,20 @@ impl HimsAuthorizationEngine {
 
 #[async_trait]
 impl AuthorizationEngine for HimsAuthorizationEngine {
-    async fn check(&self, request: AuthorizationRequest) -> AuthResult<AuthorizationResponse> {
+    async fn check(&self, mut request: AuthorizationRequest) -> AuthResult<AuthorizationResponse> {
         let start_time = Instant::now();
-        let mut reasons = Vec::new();
-        let mut requirements = Vec::new();
-        let mut restrictions = Vec::new();
-        let mut time_limit = None;
-        let mut decision = AccessDecision::Deny;
-        let mut confidence = 0.0;
+        Self::apply_session_risk(&mut request);
         
         // Validate request context
         self.validate_context(&request.context).await?;
+        self.enrich(std::slice::from_mut(&mut request)).await?;
+        self.ensure_consistency(&request.consistency).await?;
         
-        // Check emergency access first
-        if self.check_emergency_access(&request).await? {
-            decision = AccessDecision::EmergencyAccess;
-            reasons.push("Emergency access granted".to_string());
-            confidence = 1.0;
+        // Step-up authentication gates sensitive actions, including break-glass
+        let mut response = if let Some(step_up) = self.step_up_response(&request) {
+            step_up
+        } else if self.check_emergency_access(&request).await? {
+            Self::emergency_response()
         } else {
             // Evaluate policies
             let policy_decision = self.policy_engine.evaluate_policies(
@@ -351,93 +944,18 @@ impl AuthorizationEngine for HimsAuthorizationEngine {
                 &request.resource,
                 &request.context,
             ).await.map_err(|e| AuthError::PolicyEvaluation(e.to_string()))?;
+            let policy_decision = Self::apply_mfa(policy_decision, &request.session);
             
-            match policy_decision.decision {
-                PolicyEffect::Allow => {
-                    decision = AccessDecision::Allow;
-                    reasons.extend(policy_decision.reasons);
-                    confidence = policy_decision.confidence;
-                },
-                PolicyEffect::Deny => {
-                    decision = AccessDecision::Deny;
-                    reasons.extend(policy_decision.reasons);
-                    confidence = policy_decision.confidence;
-                },
-                PolicyEffect::RequireApproval => {
-                    decision = AccessDecision::RequireApproval;
-                    requirements.extend(policy_decision.requirements);
-                    confidence = policy_decision.confidence;
-                },
-                PolicyEffect::RequireSecondFactor => {
-                    decision = AccessDecision::RequireMFA;
-                    requirements.push("Multi-factor authentication required".to_string());
-                    confidence = policy_decision.confidence;
-                },
-                PolicyEffect::AuditOnly => {
-                    // Continue with relationship checks
-                    let required_relations = self.get_required_relations(&request.action, &request.resource).await?;
-                    let mut relation_found = false;
-                    
-                    for relation in required_relations {
-                        let mut visited = HashSet::new();
-                        if self.resolve_relationships(
-                            &request.resource,
-                            &request.subject,
-                            &relation,
-                            &mut visited,
-                            0
-                        ).await? {
-                            decision = AccessDecision::Allow;
-                            reasons.push(format!("Access granted via {} relationship", relation));
-                            confidence = 0.8;
-                            relation_found = true;
-                            break;
-                        }
-                    }
-                    
-                    if !relation_found {
-                        decision = AccessDecision::Deny;
-                        reasons.push("No valid relationship found".to_string());
-                        confidence = 0.9;
-                    }
-                },
-                PolicyEffect::TimeLimit(seconds) => {
-                    time_limit = Some(Duration::from_secs(seconds));
-                    decision = AccessDecision::AllowWithRestrictions;
-                    restrictions.push(format!("Time limit: {} seconds", seconds));
-                    confidence = policy_decision.confidence;
-                },
-                PolicyEffect::Restrict(restriction_list) => {
-                    decision = AccessDecision::AllowWithRestrictions;
-                    restrictions.extend(restriction_list);
-                    confidence = policy_decision.confidence;
-                },
-                PolicyEffect::Conditional(_) => {
-                    // Handle conditional policies based on additional requirements
-                    requirements.extend(policy_decision.requirements);
-                    if requirements.is_empty() {
-                        decision = AccessDecision::Allow;
-                    } else {
-                        decision = AccessDecision::RequireApproval;
-                    }
-                    confidence = policy_decision.confidence;
-                },
-            }
-        }
+            // Continue with relationship checks when policies only require
+            // auditing or limit access to listed relations
+            let relation_match = self.relation_match(&request, &policy_decision, None).await?;
+            
+            Self::response_from_policy(policy_decision, relation_match)
+        };
         
         let evaluation_time_ms = start_time.elapsed().as_millis() as u64;
-        
-        let response = AuthorizationResponse {
-            allowed: matches!(decision, AccessDecision::Allow | AccessDecision::EmergencyAccess | AccessDecision::AllowWithRestrictions),
-            decision: decision.clone(),
-            reasons,
-            requirements,
-            time_limit,
-            restrictions,
-            confidence,
-            evaluation_time_ms,
-            request_id: request.request_id.clone(),
-        };
+        response.evaluation_time_ms = evaluation_time_ms;
+        response.request_id = request.request_id.clone();
         
         // Audit the decision
         self.audit_decision(&request, &response, evaluation_time_ms).await?;
@@ -445,13 +963,175 @@ impl AuthorizationEngine for HimsAuthorizationEngine {
         Ok(response)
     }
     
+    /// However many requests a batch holds, it reads the store and the
+    /// sources in a bounded number of round trips: one wait for the freshest
+    /// revision any request needs, one lookup per urgency, label and consent
+    /// source, one hierarchy lookup per distinct subject and one relationship
+    /// check. Policies are evaluated once per distinct policy key, which for
+    /// an external engine is a call each. Decisions are audited one entry
+    /// per request, as `check` does.
+    async fn check_batch(&self, mut requests: Vec<AuthorizationRequest>) -> AuthResult<Vec<AuthorizationResponse>> {
+        let start_time = Instant::now();
+        requests.iter_mut().for_each(Self::apply_session_risk);
+        let mut outcomes: Vec<Option<AuthorizationResponse>> = Vec::with_capacity(requests.len());
+        let mut pending: Vec<(usize, PolicyDecision)> = Vec::new();
+        let mut policy_cache: HashMap<String, PolicyDecision> = HashMap::new();
+        
+        for request in &requests {
+            self.validate_context(&request.context).await?;
+        }
+        self.enrich(&mut requests).await?;
+        
+        // Once the store has the freshest revision any request needs, it has
+        // every other
+        let mut required_revision = None;
+        for request in &requests {
+            if let Consistency::AtLeastAsFresh(zookie) = &request.consistency {
+                required_revision = required_revision.max(Some(zookie.revision()?));
+            }
+        }
+        if let Some(required_revision) = required_revision {
+            self.wait_for_revision(required_revision).await?;
+        }
+        
+        for (index, request) in requests.iter().enumerate() {
+            if let Some(step_up) = self.step_up_response(request) {
+                outcomes.push(Some(step_up));
+                continue;
+            }
+            
+            if self.check_emergency_access(request).await? {
+                outcomes.push(Some(Self::emergency_response()));
+                continue;
+            }
+            
+            // Policies are shared across requests with the same subject, action,
+            // resource, session, urgency, purpose, labels and consent. Engines
+            // whose decisions only depend on the resource type share them across
+            // resources of that type too, which is the common shape of a worklist.
+            let resource_key = request.resource.to_string();
+            let resource_scope = if self.policy_engine.type_scoped() {
+                resource_key.split(':').next().unwrap_or_default()
+            } else {
+                resource_key.as_str()
+            };
+            let labels: Vec<&str> = request.context.security_labels.iter().map(|label| label.code()).collect();
+            let policy_key = format!(
+                "{}#{}#{}#{}#{}#{}#{}#{}",
+                request.subject, request.action, resource_scope,
+                request.context.session_id.as_deref().unwrap_or_default(),
+                request.context.get_urgency_level().as_str(),
+                request.context.purpose().map_or("", |purpose| purpose.as_str()),
+                labels.join(","),
+                request.context.part2_consent
+            );
+            
+            let policy_decision = match policy_cache.get(&policy_key) {
+                Some(decision) => decision.clone(),
+                None => {
+                    let decision = self.policy_engine.evaluate_policies(
+                        &request.subject,
+                        &request.action,
+                        &request.resource,
+                        &request.context,
+                    ).await.map_err(|e| AuthError::PolicyEvaluation(e.to_string()))?;
+                    policy_cache.insert(policy_key, decision.clone());
+                    decision
+                }
+            };
+            
+            let policy_decision = Self::apply_mfa(policy_decision, &request.session);
+            if matches!(policy_decision.decision, PolicyEffect::AuditOnly | PolicyEffect::RequireRelations(_)) {
+                pending.push((index, policy_decision));
+                outcomes.push(None);
+            } else {
+                outcomes.push(Some(Self::response_from_policy(policy_decision, None)));
+            }
+        }
+        
+        if !pending.is_empty() {
+            // Expand each distinct subject to the departments, organizations and
+            // groups it belongs to once, rather than once per request
+            let mut hierarchies: HashMap<Subject, Vec<Subject>> = HashMap::new();
+            for (index, _) in &pending {
+                let subject = &requests[*index].subject;
+                if !hierarchies.contains_key(subject) {
+                    let mut candidates = vec![subject.clone()];
+                    candidates.extend(
+                        self.storage.get_subject_hierarchy(subject, self.config.max_relation_depth).await?
+                    );
+                    hierarchies.insert(subject.clone(), candidates);
+                }
+            }
+            
+            // Collect every direct (resource, relation, subject) tuple that
+            // could grant access
+            let mut tuples = Vec::new();
+            let mut owners = Vec::new();
+            for (index, policy_decision) in &pending {
+                let request = &requests[*index];
+                let relations = match &policy_decision.decision {
+                    PolicyEffect::RequireRelations(relations) => self.with_parent_relations(relations.clone()),
+                    _ => self.with_parent_relations(
+                        self.get_required_relations(&request.action, &request.resource).await?
+                    ),
+                };
+                for relation in relations {
+                    for candidate in &hierarchies[&request.subject] {
+                        tuples.push((request.resource.clone(), relation.clone(), candidate.clone()));
+                        owners.push((request.resource.clone(), relation.clone(), request.subject.clone()));
+                    }
+                }
+            }
+            
+            // Resolve all of them in a single round trip. They settle every
+            // request, including relations inherited through membership and
+            // department membership, which `check` resolves one at a time.
+            let found = self.storage.check_relationships_batch(&tuples).await?;
+            let known: HashSet<(Resource, HealthcareRelation, Subject)> = owners
+                .into_iter()
+                .zip(found)
+                .filter_map(|(owner, exists)| exists.then_some(owner))
+                .collect();
+            
+            for (index, policy_decision) in pending {
+                let relation_match = self.relation_match(&requests[index], &policy_decision, Some(&known)).await?;
+                outcomes[index] = Some(Self::response_from_policy(policy_decision, relation_match));
+            }
+        }
+        
+        let evaluation_time_ms = start_time.elapsed().as_millis() as u64;
+        let mut responses = Vec::with_capacity(requests.len());
+        for (request, outcome) in requests.iter().zip(outcomes) {
+            let mut response = outcome.ok_or_else(|| {
+                AuthError::Engine("Batch authorization produced no decision".to_string())
+            })?;
+            response.evaluation_time_ms = evaluation_time_ms;
+            response.request_id = request.request_id.clone();
+            
+            self.audit_decision(request, &response, evaluation_time_ms).await?;
+            responses.push(response);
+        }
+        
+        Ok(responses)
+    }
+    
     async fn expand(
         &self,
         resource: Resource,
         relation: HealthcareRelation,
     ) -> AuthResult<Vec<Subject>> {
-        // PostgresAuthorizationStorage implements RelationStorage, so we can call directly
-        let subjects = self.storage.find_direct_relationships(&resource, &relation).await?;
+        let mut subjects = self.storage.find_direct_relationships(&resource, &relation).await?;
+        
+        // Include members of any department, organization or group granted the relation
+        let inherited = self.storage
+            .find_inherited_relationships(&resource, &relation, self.config.max_relation_depth)
+            .await?;
+        for subject in inherited {
+            if !subjects.contains(&subject) {
+                subjects.push(subject);
+            }
+        }
         
         // Update cache
         self.update_cache(&resource, &relation, subjects.clone()).await;
@@ -476,14 +1156,16 @@ impl AuthorizationEngine for HimsAuthorizationEngine {
         Ok(resources)
     }
     
-    async fn add_relationship(&self, tuple: RelationshipTuple) -> AuthResult<()> {
+    async fn add_relationship(&self, tuple: RelationshipTuple) -> AuthResult<Zookie> {
         self.storage.store_relationship(&tuple).await?;
-        Ok(())
+        self.invalidate_cache(&tuple).await;
+        self.current_zookie().await
     }
     
-    async fn remove_relationship(&self, tuple: RelationshipTuple) -> AuthResult<()> {
+    async fn remove_relationship(&self, tuple: RelationshipTuple) -> AuthResult<Zookie> {
         self.storage.remove_relationship(&tuple).await?;
-        Ok(())
+        self.invalidate_cache(&tuple).await;
+        self.current_zookie().await
     }
     
     async fn has_relationship(
@@ -495,4 +1177,638 @@ impl AuthorizationEngine for HimsAuthorizationEngine {
         let result = self.storage.has_relationship(&object, &relation, &subject).await?;
         Ok(result)
     }
-}
\ No newline at end of file
+    
+    async fn read_changes(&self, since: Option<Zookie>, limit: i64) -> AuthResult<Vec<RelationshipChange>> {
+        let since_revision = match since {
+            Some(zookie) => zookie.revision()?,
+            None => 0,
+        };
+        
+        self.storage.read_changes(since_revision, limit).await
+    }
+    
+    async fn watch(&self, since: Option<Zookie>) -> AuthResult<tokio::sync::mpsc::Receiver<RelationshipChange>> {
+        let mut revision = match since {
+            Some(zookie) => zookie.revision()?,
+            None => self.storage.current_revision().await?,
+        };
+        
+        let storage = self.storage.clone();
+        let batch_size = self.config.watch_batch_size;
+        let poll_interval = Duration::from_millis(self.config.watch_poll_interval_ms);
+        let (sender, receiver) = tokio::sync::mpsc::channel(batch_size.max(1) as usize);
+        
+        tokio::spawn(async move {
+            loop {
+                let changes = match storage.read_changes(revision, batch_size).await {
+                    Ok(changes) => changes,
+                    Err(e) => {
+                        tracing::warn!("Authorization watch failed to read changelog: {}", e);
+                        tokio::time::sleep(poll_interval).await;
+                        continue;
+                    }
+                };
+                
+                let caught_up = (changes.len() as i64) < batch_size;
+                for change in changes {
+                    revision = change.revision;
+                    if sender.send(change).await.is_err() {
+                        // Watcher went away
+                        return;
+                    }
+                }
+                
+                if caught_up {
+                    if sender.is_closed() {
+                        return;
+                    }
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        });
+        
+        Ok(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use super::super::memory_storage::InMemoryAuthorizationStorage;
+    use super::super::audit::AuditEntry;
+    use super::super::policies::HealthcarePolicy;
+    use super::super::storage::{
+        AuthorizationStorage, ChangelogStorage, PolicyStorage, PolicyUsageStats, RelationStorage,
+    };
+    use super::super::StorageBackend;
+    
+    fn engine(storage: Arc<InMemoryAuthorizationStorage>) -> HimsAuthorizationEngine {
+        HimsAuthorizationEngine::new(
+            storage,
+            Arc::new(HimsPolicyEngine::new()),
+            Arc::new(AuditManager::new(AuditConfig::default())),
+            AuthorizationConfig::default(),
+        )
+    }
+    
+    async fn relate(
+        storage: &InMemoryAuthorizationStorage,
+        object: Resource,
+        relation: HealthcareRelation,
+        subject: Subject,
+    ) {
+        storage.store_relationship(&RelationshipTuple::new(object, relation, subject)).await.unwrap();
+    }
+    
+    fn request(user: Uuid, action: Action, resource: Resource) -> AuthorizationRequest {
+        AuthorizationRequest {
+            subject: Subject::User(user),
+            action,
+            resource,
+            context: RequestContext {
+                timestamp: Utc.with_ymd_and_hms(2024, 3, 4, 10, 0, 0).unwrap(),
+                ..RequestContext::new()
+            }
+            .with_purpose_of_use(PurposeOfUse::Treatment),
+            session: SessionContext {
+                user_id: user,
+                session_id: "session".to_string(),
+                ip_address: None,
+                user_agent: None,
+                department_id: None,
+                location_id: None,
+                shift_id: None,
+                mfa_verified: false,
+                mfa_verified_at: None,
+                risk_score: 0.0,
+            },
+            request_id: None,
+            consistency: Consistency::default(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_department_membership_needs_department_relation() {
+        let storage = Arc::new(InMemoryAuthorizationStorage::new());
+        let engine = engine(storage.clone());
+        let (department, organization, nurse) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (ward_round, clinic_visit, org_visit) = (
+            Resource::Appointment(Uuid::new_v4()),
+            Resource::Appointment(Uuid::new_v4()),
+            Resource::Appointment(Uuid::new_v4()),
+        );
+        
+        relate(&storage, Resource::Department(department), HealthcareRelation::DepartmentMember, Subject::User(nurse))
+            .await;
+        relate(
+            &storage,
+            Resource::Organization(organization),
+            HealthcareRelation::DepartmentMember,
+            Subject::Department(department),
+        ).await;
+        relate(&storage, ward_round.clone(), HealthcareRelation::DepartmentMember, Subject::Department(department))
+            .await;
+        relate(&storage, clinic_visit.clone(), HealthcareRelation::CareTeamMember, Subject::Department(department))
+            .await;
+        relate(&storage, org_visit.clone(), HealthcareRelation::BillingAccess, Subject::Organization(organization))
+            .await;
+        
+        assert!(engine.check_department_membership(&ward_round, nurse).await.unwrap());
+        assert!(!engine.check_department_membership(&clinic_visit, nurse).await.unwrap());
+        assert!(!engine.check_department_membership(&org_visit, nurse).await.unwrap());
+        assert!(!engine.check_department_membership(&ward_round, Uuid::new_v4()).await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_check_batch_matches_check() {
+        let storage = Arc::new(InMemoryAuthorizationStorage::new());
+        let engine = engine(storage.clone());
+        let (doctor, nurse, clerk, department) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (patient, ward_round, clinic_visit) = (
+            Resource::Patient(Uuid::new_v4()),
+            Resource::Appointment(Uuid::new_v4()),
+            Resource::Appointment(Uuid::new_v4()),
+        );
+        
+        relate(&storage, patient.clone(), HealthcareRelation::PrimaryPhysician, Subject::User(doctor)).await;
+        relate(&storage, patient.clone(), HealthcareRelation::CareTeamMember, Subject::Department(department)).await;
+        relate(&storage, Resource::Department(department), HealthcareRelation::DepartmentMember, Subject::User(nurse))
+            .await;
+        relate(&storage, Resource::Department(department), HealthcareRelation::DepartmentHead, Subject::User(doctor))
+            .await;
+        relate(&storage, ward_round.clone(), HealthcareRelation::DepartmentMember, Subject::Department(department))
+            .await;
+        relate(&storage, clinic_visit.clone(), HealthcareRelation::CareTeamMember, Subject::Department(department))
+            .await;
+        
+        let cases = [
+            (doctor, Action::Read, patient.clone(), true),
+            (nurse, Action::Read, patient.clone(), true),
+            (clerk, Action::Read, patient.clone(), false),
+            (doctor, Action::Write, patient.clone(), true),
+            (nurse, Action::Write, patient.clone(), false),
+            (nurse, Action::Schedule, ward_round.clone(), true),
+            (doctor, Action::Schedule, ward_round.clone(), true),
+            (nurse, Action::Schedule, clinic_visit.clone(), false),
+            (clerk, Action::Schedule, ward_round.clone(), false),
+        ];
+        
+        let requests = cases
+            .iter()
+            .map(|(user, action, resource, _)| request(*user, action.clone(), resource.clone()))
+            .collect();
+        let batch = engine.check_batch(requests).await.unwrap();
+        for ((user, action, resource, allowed), batched) in cases.into_iter().zip(batch) {
+            let single = engine.check(request(user, action.clone(), resource.clone())).await.unwrap();
+            assert_eq!(single.allowed, allowed, "check of {} on {}", action, resource);
+            assert_eq!(batched.allowed, single.allowed, "check_batch of {} on {}", action, resource);
+            assert_eq!(batched.reasons, single.reasons);
+        }
+    }
+    
+    /// In-memory storage counting the calls made to it
+    #[derive(Default)]
+    struct CountingStorage {
+        inner: InMemoryAuthorizationStorage,
+        calls: std::sync::Mutex<HashMap<&'static str, usize>>,
+    }
+    
+    impl CountingStorage {
+        fn count(&self, method: &'static str) {
+            *self.calls.lock().unwrap().entry(method).or_default() += 1;
+        }
+        
+        fn calls(&self, method: &str) -> usize {
+            self.calls.lock().unwrap().get(method).copied().unwrap_or_default()
+        }
+    }
+    
+    #[async_trait]
+    impl AuthorizationStorage for CountingStorage {
+        async fn store_relationship(&self, tuple: &RelationshipTuple) -> AuthResult<()> {
+            self.inner.store_relationship(tuple).await
+        }
+        async fn remove_relationship(&self, tuple: &RelationshipTuple) -> AuthResult<()> {
+            self.inner.remove_relationship(tuple).await
+        }
+        async fn has_relationship(
+            &self,
+            object: &Resource,
+            relation: &HealthcareRelation,
+            subject: &Subject,
+        ) -> AuthResult<bool> {
+            self.count("has_relationship");
+            self.inner.has_relationship(object, relation, subject).await
+        }
+        async fn check_relationships_batch(
+            &self,
+            tuples: &[(Resource, HealthcareRelation, Subject)],
+        ) -> AuthResult<Vec<bool>> {
+            self.count("check_relationships_batch");
+            self.inner.check_relationships_batch(tuples).await
+        }
+        async fn get_relationships_for_resource(&self, resource: &Resource) -> AuthResult<Vec<RelationshipTuple>> {
+            self.count("get_relationships_for_resource");
+            self.inner.get_relationships_for_resource(resource).await
+        }
+        async fn get_relationships_for_subject(&self, subject: &Subject) -> AuthResult<Vec<RelationshipTuple>> {
+            self.count("get_relationships_for_subject");
+            self.inner.get_relationships_for_subject(subject).await
+        }
+        async fn store_policy(&self, policy: &HealthcarePolicy) -> AuthResult<()> {
+            self.inner.store_policy(policy).await
+        }
+        async fn get_policy(&self, policy_id: &str) -> AuthResult<Option<HealthcarePolicy>> {
+            self.inner.get_policy(policy_id).await
+        }
+        async fn get_active_policies(&self) -> AuthResult<Vec<HealthcarePolicy>> {
+            self.inner.get_active_policies().await
+        }
+        async fn store_audit_entry(&self, entry: &AuditEntry) -> AuthResult<()> {
+            self.count("store_audit_entry");
+            self.inner.store_audit_entry(entry).await
+        }
+        async fn cleanup_expired_relationships(&self) -> AuthResult<u64> {
+            self.inner.cleanup_expired_relationships().await
+        }
+    }
+    
+    #[async_trait]
+    impl RelationStorage for CountingStorage {
+        async fn find_direct_relationships(
+            &self,
+            object: &Resource,
+            relation: &HealthcareRelation,
+        ) -> AuthResult<Vec<Subject>> {
+            self.count("find_direct_relationships");
+            self.inner.find_direct_relationships(object, relation).await
+        }
+        async fn find_inherited_relationships(
+            &self,
+            object: &Resource,
+            relation: &HealthcareRelation,
+            max_depth: u8,
+        ) -> AuthResult<Vec<Subject>> {
+            self.count("find_inherited_relationships");
+            self.inner.find_inherited_relationships(object, relation, max_depth).await
+        }
+        async fn get_subject_hierarchy(&self, subject: &Subject, max_depth: u8) -> AuthResult<Vec<Subject>> {
+            self.count("get_subject_hierarchy");
+            self.inner.get_subject_hierarchy(subject, max_depth).await
+        }
+    }
+    
+    #[async_trait]
+    impl PolicyStorage for CountingStorage {
+        async fn get_policies_by_type(&self, policy_type: &str) -> AuthResult<Vec<HealthcarePolicy>> {
+            self.inner.get_policies_by_type(policy_type).await
+        }
+        async fn update_policy_status(&self, policy_id: &str, is_active: bool) -> AuthResult<()> {
+            self.inner.update_policy_status(policy_id, is_active).await
+        }
+        async fn get_policy_usage_stats(&self, policy_id: &str) -> AuthResult<PolicyUsageStats> {
+            self.inner.get_policy_usage_stats(policy_id).await
+        }
+    }
+    
+    #[async_trait]
+    impl ChangelogStorage for CountingStorage {
+        async fn read_changes(&self, since_revision: i64, limit: i64) -> AuthResult<Vec<RelationshipChange>> {
+            self.inner.read_changes(since_revision, limit).await
+        }
+        async fn current_revision(&self) -> AuthResult<i64> {
+            self.count("current_revision");
+            self.inner.current_revision().await
+        }
+    }
+    
+    /// Urgency, label and consent sources counting their lookups. Patients
+    /// in `substance_use` have their records labelled, and consented to
+    /// disclosures of them.
+    #[derive(Default)]
+    struct CountingSources {
+        substance_use: HashSet<Uuid>,
+        lookups: std::sync::Mutex<HashMap<&'static str, usize>>,
+    }
+    
+    impl CountingSources {
+        fn count(&self, source: &'static str) {
+            *self.lookups.lock().unwrap().entry(source).or_default() += 1;
+        }
+        
+        fn lookups(&self, source: &str) -> usize {
+            self.lookups.lock().unwrap().get(source).copied().unwrap_or_default()
+        }
+        
+        fn labels(&self, patient_id: Option<Uuid>) -> Vec<ConfidentialityLabel> {
+            match patient_id {
+                Some(patient_id) if self.substance_use.contains(&patient_id) => vec![ConfidentialityLabel::SubstanceAbuse],
+                _ => vec![],
+            }
+        }
+    }
+    
+    #[async_trait]
+    impl UrgencySource for CountingSources {
+        async fn patient_urgency(&self, _patient_id: Uuid) -> AuthResult<Option<UrgencyLevel>> {
+            self.count("urgency");
+            Ok(None)
+        }
+        async fn patient_urgency_batch(&self, _patient_ids: &[Uuid]) -> AuthResult<HashMap<Uuid, UrgencyLevel>> {
+            self.count("urgency");
+            Ok(HashMap::new())
+        }
+    }
+    
+    #[async_trait]
+    impl SecurityLabelSource for CountingSources {
+        async fn security_labels(
+            &self,
+            _resource: &Resource,
+            patient_id: Option<Uuid>,
+        ) -> AuthResult<Vec<ConfidentialityLabel>> {
+            self.count("labels");
+            Ok(self.labels(patient_id))
+        }
+        async fn security_labels_batch(
+            &self,
+            resources: &[(Resource, Option<Uuid>)],
+        ) -> AuthResult<Vec<Vec<ConfidentialityLabel>>> {
+            self.count("labels");
+            Ok(resources.iter().map(|(_, patient_id)| self.labels(*patient_id)).collect())
+        }
+    }
+    
+    #[async_trait]
+    impl ConsentSource for CountingSources {
+        async fn part2_consent(&self, patient_id: Uuid, _recipient: &Subject, _purpose: PurposeOfUse) -> AuthResult<bool> {
+            self.count("consent");
+            Ok(self.substance_use.contains(&patient_id))
+        }
+        async fn part2_consent_batch(&self, disclosures: &[(Uuid, Subject, PurposeOfUse)]) -> AuthResult<Vec<bool>> {
+            self.count("consent");
+            Ok(disclosures.iter().map(|(patient_id, _, _)| self.substance_use.contains(patient_id)).collect())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_check_batch_round_trips_do_not_grow_with_the_batch() {
+        let storage = Arc::new(CountingStorage::default());
+        let (nurses, department, treated) = (
+            [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()],
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let patients: Vec<Uuid> = (0..8).map(|_| Uuid::new_v4()).collect();
+        let sources = Arc::new(CountingSources {
+            substance_use: HashSet::from([treated]),
+            ..CountingSources::default()
+        });
+        let engine = HimsAuthorizationEngine::new(
+            storage.clone(),
+            Arc::new(HimsPolicyEngine::new()),
+            Arc::new(AuditManager::new(AuditConfig::default())),
+            AuthorizationConfig::default(),
+        )
+        .with_urgency_source(sources.clone())
+        .with_label_source(sources.clone())
+        .with_consent_source(sources.clone());
+        
+        // The first patients are cared for by the nurses' department, and
+        // the others by nobody, so their requests are denied
+        for nurse in nurses {
+            storage
+                .store_relationship(&RelationshipTuple::new(
+                    Resource::Department(department),
+                    HealthcareRelation::DepartmentMember,
+                    Subject::User(nurse),
+                ))
+                .await
+                .unwrap();
+        }
+        for patient in patients.iter().take(4).chain([&treated]) {
+            storage
+                .store_relationship(&RelationshipTuple::new(
+                    Resource::Patient(*patient),
+                    HealthcareRelation::CareTeamMember,
+                    Subject::Department(department),
+                ))
+                .await
+                .unwrap();
+        }
+        
+        let cases: Vec<(Uuid, Uuid)> = nurses
+            .iter()
+            .flat_map(|nurse| patients.iter().chain([&treated]).map(move |patient| (*nurse, *patient)))
+            .collect();
+        let requests = cases
+            .iter()
+            .map(|(nurse, patient)| request(*nurse, Action::Read, Resource::Patient(*patient)))
+            .collect();
+        let batch = engine.check_batch(requests).await.unwrap();
+        
+        assert_eq!(storage.calls("check_relationships_batch"), 1);
+        assert_eq!(storage.calls("get_subject_hierarchy"), nurses.len());
+        for resolution in ["has_relationship", "find_inherited_relationships", "get_relationships_for_resource"] {
+            assert_eq!(storage.calls(resolution), 0, "{} during check_batch", resolution);
+        }
+        assert_eq!(storage.calls("current_revision"), 0);
+        assert_eq!(storage.calls("store_audit_entry"), cases.len());
+        for source in ["urgency", "labels", "consent"] {
+            assert_eq!(sources.lookups(source), 1, "{} lookups during check_batch", source);
+        }
+        
+        for ((nurse, patient), batched) in cases.into_iter().zip(batch) {
+            let single = engine.check(request(nurse, Action::Read, Resource::Patient(patient))).await.unwrap();
+            assert_eq!(batched.allowed, single.allowed, "check_batch of patient {}", patient);
+            assert_eq!(batched.reasons, single.reasons);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_from_config_storage() {
+        let postgres = AuthorizationConfig::default();
+        assert!(matches!(
+            HimsAuthorizationEngine::from_config(postgres, None).await,
+            Err(AuthError::Configuration(_))
+        ));
+        
+        let memory = AuthorizationConfig {
+            storage_backend: StorageBackend::InMemory,
+            ..AuthorizationConfig::default()
+        };
+        let engine = HimsAuthorizationEngine::from_config(memory, None).await.unwrap();
+        let (patient, doctor) = (Resource::Patient(Uuid::new_v4()), Uuid::new_v4());
+        assert!(!engine.check(request(doctor, Action::Read, patient.clone())).await.unwrap().allowed);
+        engine
+            .add_relationship(RelationshipTuple::new(
+                patient.clone(),
+                HealthcareRelation::PrimaryPhysician,
+                Subject::User(doctor),
+            ))
+            .await
+            .unwrap();
+        assert!(engine.check(request(doctor, Action::Read, patient)).await.unwrap().allowed);
+    }
+    
+    #[tokio::test]
+    async fn test_from_config_external_policy() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        use super::super::ExternalPolicyConfig;
+        
+        let opa = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/data/hims/authz/decision"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "result": true })))
+            .mount(&opa)
+            .await;
+        let config = |endpoint: String| AuthorizationConfig {
+            storage_backend: StorageBackend::InMemory,
+            policy_backend: PolicyBackend::External(ExternalPolicyConfig::opa(
+                endpoint,
+                "hims/authz/decision".to_string(),
+            )),
+            ..AuthorizationConfig::default()
+        };
+        
+        // OPA allows without any relationship the built-in policies would need
+        let engine = HimsAuthorizationEngine::from_config(config(opa.uri()), None).await.unwrap();
+        let response = engine.check(request(Uuid::new_v4(), Action::Read, Resource::Patient(Uuid::new_v4())))
+            .await
+            .unwrap();
+        assert!(response.allowed);
+        assert_eq!(opa.received_requests().await.unwrap().len(), 1);
+        
+        // An unreachable engine fails closed
+        let engine = HimsAuthorizationEngine::from_config(config("http://127.0.0.1:9".to_string()), None)
+            .await
+            .unwrap();
+        let response = engine.check(request(Uuid::new_v4(), Action::Read, Resource::Patient(Uuid::new_v4())))
+            .await
+            .unwrap();
+        assert!(!response.allowed);
+    }
+    
+    #[tokio::test]
+    async fn test_check_batch_per_resource_policies() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        use super::super::ExternalPolicyConfig;
+        
+        // OPA allows one record and denies every other
+        let (shared, private) = (Uuid::new_v4(), Uuid::new_v4());
+        let opa = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "input": { "resource": { "id": shared.to_string() } } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "result": true })))
+            .with_priority(1)
+            .mount(&opa)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "result": false })))
+            .mount(&opa)
+            .await;
+        let config = AuthorizationConfig {
+            storage_backend: StorageBackend::InMemory,
+            policy_backend: PolicyBackend::External(ExternalPolicyConfig::opa(
+                opa.uri(),
+                "hims/authz/decision".to_string(),
+            )),
+            ..AuthorizationConfig::default()
+        };
+        let engine = HimsAuthorizationEngine::from_config(config, None).await.unwrap();
+        let user = Uuid::new_v4();
+        
+        let records = [Resource::MedicalRecord(shared), Resource::MedicalRecord(private)];
+        let batch = engine
+            .check_batch(records.iter().map(|record| request(user, Action::Read, record.clone())).collect())
+            .await
+            .unwrap();
+        for (record, batched) in records.into_iter().zip(batch) {
+            let single = engine.check(request(user, Action::Read, record.clone())).await.unwrap();
+            assert_eq!(batched.allowed, single.allowed, "check_batch of {}", record);
+        }
+        assert!(engine.check(request(user, Action::Read, Resource::MedicalRecord(shared))).await.unwrap().allowed);
+        assert!(!engine.check(request(user, Action::Read, Resource::MedicalRecord(private))).await.unwrap().allowed);
+    }
+    
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_from_config_sqlite() {
+        let config = AuthorizationConfig {
+            storage_backend: StorageBackend::Sqlite { database_url: "sqlite::memory:".to_string() },
+            ..AuthorizationConfig::default()
+        };
+        let engine = HimsAuthorizationEngine::from_config(config, None).await.unwrap();
+        let (patient, nurse, department) = (Resource::Patient(Uuid::new_v4()), Uuid::new_v4(), Uuid::new_v4());
+        
+        // Access inherited through the department, decided on the SQLite tables
+        for tuple in [
+            RelationshipTuple::new(
+                patient.clone(),
+                HealthcareRelation::CareTeamMember,
+                Subject::Department(department),
+            ),
+            RelationshipTuple::new(
+                Resource::Department(department),
+                HealthcareRelation::DepartmentMember,
+                Subject::User(nurse),
+            ),
+        ] {
+            engine.add_relationship(tuple).await.unwrap();
+        }
+        assert!(engine.check(request(nurse, Action::Read, patient.clone())).await.unwrap().allowed);
+        assert!(!engine.check(request(Uuid::new_v4(), Action::Read, patient)).await.unwrap().allowed);
+        assert_eq!(engine.read_changes(None, 10).await.unwrap().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_zookie_read_after_write() {
+        let storage = Arc::new(InMemoryAuthorizationStorage::new());
+        let engine = HimsAuthorizationEngine::new(
+            storage.clone(),
+            Arc::new(HimsPolicyEngine::new()),
+            Arc::new(AuditManager::new(AuditConfig::default())),
+            AuthorizationConfig {
+                consistency_wait_ms: 100,
+                ..AuthorizationConfig::default()
+            },
+        );
+        let (patient, doctor) = (Resource::Patient(Uuid::new_v4()), Uuid::new_v4());
+        
+        let zookie = engine
+            .add_relationship(RelationshipTuple::new(
+                patient.clone(),
+                HealthcareRelation::PrimaryPhysician,
+                Subject::User(doctor),
+            ))
+            .await
+            .unwrap();
+        let mut fresh = request(doctor, Action::Read, patient.clone());
+        fresh.consistency = Consistency::AtLeastAsFresh(zookie.clone());
+        assert!(engine.check(fresh).await.unwrap().allowed);
+        
+        let mut ahead = request(doctor, Action::Read, patient);
+        ahead.consistency = Consistency::AtLeastAsFresh(Zookie::from_revision(zookie.revision().unwrap() + 1));
+        assert!(matches!(engine.check(ahead).await, Err(AuthError::Consistency(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_fully_consistent_bypasses_cache() {
+        let storage = Arc::new(InMemoryAuthorizationStorage::new());
+        let engine = engine(storage.clone());
+        let (patient, doctor) = (Resource::Patient(Uuid::new_v4()), Uuid::new_v4());
+        
+        // Cache that nobody is the primary physician, then grant the relation
+        // behind the engine's back so the cache goes stale
+        engine.expand(patient.clone(), HealthcareRelation::PrimaryPhysician).await.unwrap();
+        relate(&storage, patient.clone(), HealthcareRelation::PrimaryPhysician, Subject::User(doctor)).await;
+        
+        assert!(!engine.check(request(doctor, Action::Read, patient.clone())).await.unwrap().allowed);
+        let mut consistent = request(doctor, Action::Read, patient);
+        consistent.consistency = Consistency::FullyConsistent;
+        assert!(engine.check(consistent).await.unwrap().allowed);
+    }
+}
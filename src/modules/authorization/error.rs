@@ -50,6 +50,9 @@ pub enum AuthError {
     
     #[error("Circular dependency detected")]
     CircularDependency,
+    
+    #[error("Consistency requirement not met: {0}")]
+    Consistency(String),
 }
 
 pub type AuthResult<T> = Result<T, AuthError>;
\ No newline at end of file
@@ -0,0 +1,399 @@
+// src/modules/authorization/external_policy.rs
+//! External policy engine adapter
+//!
+//! Delegates policy evaluation to an Open Policy Agent (OPA) server or a Cedar
+//! authorization service over HTTP, so organizations can reuse existing policy
+//! infrastructure. The request context is flattened into the engine's input
+//! document and the response is mapped back onto a `PolicyDecision`.
+
+use anyhow::{Result, anyhow, Context};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::relations::{Action, Resource, Subject};
+use super::healthcare_context::RequestContext;
+use super::policies::{HealthcarePolicy, PolicyDecision, PolicyEffect, PolicyEngine};
+
+/// Namespace used for Cedar entity identifiers
+const CEDAR_NAMESPACE: &str = "HIMS";
+
+/// Which external engine to call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ExternalPolicyBackend {
+    /// OPA Data API, e.g. `POST /v1/data/hims/authz/decision`
+    Opa { decision_path: String },
+    /// Cedar authorization service, e.g. `POST /v1/is_authorized`
+    Cedar { authorize_path: String },
+}
+
+/// Configuration for the external policy engine adapter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalPolicyConfig {
+    /// Base URL of the policy engine, e.g. `http://opa:8181`
+    pub endpoint: String,
+    /// Engine flavour and decision path
+    pub backend: ExternalPolicyBackend,
+    /// Optional bearer token sent with every request
+    pub auth_token: Option<String>,
+    /// Request timeout in milliseconds
+    pub timeout_ms: u64,
+    /// Deny instead of failing the request when the engine is unreachable
+    pub fail_closed: bool,
+}
+
+impl ExternalPolicyConfig {
+    /// Configuration for an OPA server
+    pub fn opa(endpoint: String, decision_path: String) -> Self {
+        Self {
+            endpoint,
+            backend: ExternalPolicyBackend::Opa { decision_path },
+            auth_token: None,
+            timeout_ms: 500,
+            fail_closed: true,
+        }
+    }
+
+    /// Configuration for a Cedar authorization service
+    pub fn cedar(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            backend: ExternalPolicyBackend::Cedar { authorize_path: "v1/is_authorized".to_string() },
+            auth_token: None,
+            timeout_ms: 500,
+            fail_closed: true,
+        }
+    }
+
+    /// Set the bearer token used to authenticate to the engine
+    pub fn with_auth_token(mut self, token: String) -> Self {
+        self.auth_token = Some(token);
+        self
+    }
+}
+
+/// Structured decision returned by an OPA policy
+#[derive(Debug, Clone, Default, Deserialize)]
+struct OpaDecision {
+    #[serde(default)]
+    allow: bool,
+    effect: Option<String>,
+    #[serde(default)]
+    reasons: Vec<String>,
+    #[serde(default)]
+    requirements: Vec<String>,
+    #[serde(default)]
+    restrictions: Vec<String>,
+    time_limit: Option<u64>,
+    #[serde(default)]
+    policies: Vec<String>,
+}
+
+/// Response from a Cedar authorization service
+#[derive(Debug, Clone, Deserialize)]
+struct CedarResponse {
+    decision: String,
+    #[serde(default)]
+    diagnostics: CedarDiagnostics,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct CedarDiagnostics {
+    #[serde(default)]
+    reason: Vec<String>,
+    #[serde(default)]
+    errors: Vec<String>,
+}
+
+/// Policy engine that delegates evaluation to OPA or Cedar
+pub struct ExternalPolicyEngine {
+    client: reqwest::Client,
+    config: ExternalPolicyConfig,
+}
+
+impl ExternalPolicyEngine {
+    /// Create a new adapter
+    pub fn new(config: ExternalPolicyConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .context("Failed to build policy engine HTTP client")?;
+
+        Ok(Self { client, config })
+    }
+
+    /// Split a `namespace:id` display string into its parts
+    fn split_entity(entity: &str) -> (String, String) {
+        match entity.split_once(':') {
+            Some((namespace, id)) => (namespace.to_string(), id.to_string()),
+            None => (entity.to_string(), String::new()),
+        }
+    }
+
+    /// Derived context attributes policies commonly need
+    fn context_attributes(context: &RequestContext) -> Value {
+        json!({
+            "session_id": context.session_id,
+            "ip_address": context.ip_address,
+            "timestamp": context.timestamp.to_rfc3339(),
+            "is_emergency": context.is_emergency(),
+            "urgency_level": context.get_urgency_level(),
+            "security_level": context.get_security_level(),
+            "is_remote_access": context.is_remote_access(),
+            "is_after_hours": context.is_after_hours(),
+            "is_weekend": context.is_weekend(),
+            "endpoint": context.endpoint,
+            "method": context.method,
+            "patient_id": context.clinical.as_ref().and_then(|c| c.patient_id),
+            "purpose_of_use": context.purpose().map(|purpose| purpose.code()),
+            "security_labels": context.security_labels.iter().map(|label| label.code()).collect::<Vec<_>>(),
+            "part2_consent": context.part2_consent,
+        })
+    }
+
+    /// Build the OPA `input` document
+    fn opa_input(subject: &Subject, action: &Action, resource: &Resource, context: &RequestContext) -> Value {
+        let (subject_type, subject_id) = Self::split_entity(&subject.to_string());
+        let (resource_type, resource_id) = Self::split_entity(&resource.to_string());
+
+        json!({
+            "input": {
+                "subject": { "type": subject_type, "id": subject_id },
+                "action": action.to_string(),
+                "resource": { "type": resource_type, "id": resource_id },
+                "context": Self::context_attributes(context),
+                "request_context": context,
+            }
+        })
+    }
+
+    /// Format a Cedar entity UID, e.g. `HIMS::MedicalRecord::"<uuid>"`
+    fn cedar_entity(namespace: &str, id: &str) -> String {
+        let type_name: String = namespace
+            .split('_')
+            .map(|part| {
+                let mut chars = part.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                    None => String::new(),
+                }
+            })
+            .collect();
+        format!("{}::{}::\"{}\"", CEDAR_NAMESPACE, type_name, id)
+    }
+
+    /// Build a Cedar authorization request
+    fn cedar_request(subject: &Subject, action: &Action, resource: &Resource, context: &RequestContext) -> Value {
+        let (subject_type, subject_id) = Self::split_entity(&subject.to_string());
+        let (resource_type, resource_id) = Self::split_entity(&resource.to_string());
+
+        json!({
+            "principal": Self::cedar_entity(&subject_type, &subject_id),
+            "action": Self::cedar_entity("action", &action.to_string()),
+            "resource": Self::cedar_entity(&resource_type, &resource_id),
+            "context": Self::context_attributes(context),
+        })
+    }
+
+    /// Map an effect name from an external engine to a `PolicyEffect`
+    fn parse_effect(effect: &str, decision: &OpaDecision) -> PolicyEffect {
+        match effect {
+            "allow" => PolicyEffect::Allow,
+            "require_approval" => PolicyEffect::RequireApproval,
+            "require_second_factor" | "require_mfa" => PolicyEffect::RequireSecondFactor,
+            "audit_only" => PolicyEffect::AuditOnly,
+            "time_limit" => PolicyEffect::TimeLimit(decision.time_limit.unwrap_or(3600)),
+            "restrict" => PolicyEffect::Restrict(decision.restrictions.clone()),
+            _ => PolicyEffect::Deny,
+        }
+    }
+
+    /// Map the OPA `result` value (a boolean or a structured decision)
+    fn map_opa_result(result: Option<Value>) -> Result<PolicyDecision> {
+        let decision = match result {
+            Some(Value::Bool(allow)) => OpaDecision { allow, ..Default::default() },
+            Some(value @ Value::Object(_)) => serde_json::from_value::<OpaDecision>(value)
+                .context("Failed to parse OPA decision document")?,
+            Some(other) => return Err(anyhow!("Unexpected OPA result: {}", other)),
+            // An undefined decision means no rule matched
+            None => OpaDecision::default(),
+        };
+
+        let effect = match &decision.effect {
+            Some(effect) => Self::parse_effect(effect, &decision),
+            None if decision.allow => PolicyEffect::Allow,
+            None => PolicyEffect::Deny,
+        };
+
+        let mut reasons = decision.reasons.clone();
+        if reasons.is_empty() {
+            reasons.push(format!("OPA decision: {}", if decision.allow { "allow" } else { "deny" }));
+        }
+
+        Ok(PolicyDecision {
+            decision: effect,
+            applied_policies: decision.policies.clone(),
+            reasons,
+            requirements: decision.requirements.clone(),
+            confidence: 0.9,
+            restrictions: decision.restrictions.clone(),
+            time_limit: decision.time_limit,
+        })
+    }
+
+    /// Map a Cedar response
+    fn map_cedar_response(response: CedarResponse) -> Result<PolicyDecision> {
+        if !response.diagnostics.errors.is_empty() {
+            return Err(anyhow!("Cedar evaluation errors: {}", response.diagnostics.errors.join("; ")));
+        }
+
+        let allowed = response.decision.eq_ignore_ascii_case("allow");
+        let mut reasons: Vec<String> = response.diagnostics.reason
+            .iter()
+            .map(|policy_id| format!("Cedar policy '{}' applied", policy_id))
+            .collect();
+        if reasons.is_empty() {
+            reasons.push(format!("Cedar decision: {}", response.decision));
+        }
+
+        Ok(PolicyDecision {
+            decision: if allowed { PolicyEffect::Allow } else { PolicyEffect::Deny },
+            applied_policies: response.diagnostics.reason,
+            reasons,
+            requirements: Vec::new(),
+            confidence: 0.9,
+            restrictions: Vec::new(),
+            time_limit: None,
+        })
+    }
+
+    /// Send a request to the engine
+    async fn post(&self, path: &str, body: &Value) -> Result<Value> {
+        let url = format!("{}/{}", self.config.endpoint.trim_end_matches('/'), path.trim_start_matches('/'));
+        let mut request = self.client.post(&url).json(body);
+        if let Some(token) = &self.config.auth_token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await
+            .with_context(|| format!("Failed to reach policy engine at {}", url))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("Policy engine returned HTTP {}", status));
+        }
+
+        response.json::<Value>().await.context("Failed to parse policy engine response")
+    }
+
+    async fn evaluate_remote(
+        &self,
+        subject: &Subject,
+        action: &Action,
+        resource: &Resource,
+        context: &RequestContext,
+    ) -> Result<PolicyDecision> {
+        match &self.config.backend {
+            ExternalPolicyBackend::Opa { decision_path } => {
+                let path = format!("v1/data/{}", decision_path.trim_start_matches('/'));
+                let body = Self::opa_input(subject, action, resource, context);
+                let response = self.post(&path, &body).await?;
+                Self::map_opa_result(response.get("result").cloned())
+            },
+            ExternalPolicyBackend::Cedar { authorize_path } => {
+                let body = Self::cedar_request(subject, action, resource, context);
+                let response = self.post(authorize_path, &body).await?;
+                let response: CedarResponse = serde_json::from_value(response)
+                    .context("Failed to parse Cedar response")?;
+                Self::map_cedar_response(response)
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl PolicyEngine for ExternalPolicyEngine {
+    async fn evaluate_policies(
+        &self,
+        subject: &Subject,
+        action: &Action,
+        resource: &Resource,
+        context: &RequestContext,
+    ) -> Result<PolicyDecision> {
+        match self.evaluate_remote(subject, action, resource, context).await {
+            Ok(decision) => Ok(decision),
+            Err(e) if self.config.fail_closed => {
+                tracing::error!("External policy evaluation failed, denying: {}", e);
+                Ok(PolicyDecision {
+                    decision: PolicyEffect::Deny,
+                    applied_policies: Vec::new(),
+                    reasons: vec![format!("External policy engine unavailable: {}", e)],
+                    requirements: Vec::new(),
+                    confidence: 1.0,
+                    restrictions: Vec::new(),
+                    time_limit: None,
+                })
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_applicable_policies(
+        &self,
+        _subject: &Subject,
+        _action: &Action,
+        _resource: &Resource,
+    ) -> Result<Vec<HealthcarePolicy>> {
+        // Policies live in the external engine and are not visible here
+        Ok(Vec::new())
+    }
+
+    async fn add_policy(&self, _policy: HealthcarePolicy) -> Result<()> {
+        Err(anyhow!("Policies are managed by the external policy engine"))
+    }
+
+    async fn remove_policy(&self, _policy_id: &str) -> Result<()> {
+        Err(anyhow!("Policies are managed by the external policy engine"))
+    }
+
+    async fn update_policy(&self, _policy: HealthcarePolicy) -> Result<()> {
+        Err(anyhow!("Policies are managed by the external policy engine"))
+    }
+
+    async fn get_policy(&self, _policy_id: &str) -> Result<Option<HealthcarePolicy>> {
+        Ok(None)
+    }
+
+    async fn list_active_policies(&self) -> Result<Vec<HealthcarePolicy>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cedar_entity_format() {
+        assert_eq!(
+            ExternalPolicyEngine::cedar_entity("medical_record", "abc"),
+            "HIMS::MedicalRecord::\"abc\""
+        );
+    }
+
+    #[test]
+    fn test_map_opa_result() {
+        let decision = ExternalPolicyEngine::map_opa_result(Some(json!(true))).unwrap();
+        assert!(matches!(decision.decision, PolicyEffect::Allow));
+
+        let decision = ExternalPolicyEngine::map_opa_result(None).unwrap();
+        assert!(matches!(decision.decision, PolicyEffect::Deny));
+
+        let decision = ExternalPolicyEngine::map_opa_result(Some(json!({
+            "effect": "require_second_factor",
+            "reasons": ["remote access"]
+        }))).unwrap();
+        assert!(matches!(decision.decision, PolicyEffect::RequireSecondFactor));
+        assert_eq!(decision.reasons, vec!["remote access".to_string()]);
+    }
+}
@@ -35,6 +35,20 @@ pub struct RequestContext {
     pub endpoint: Option<String>,
     /// HTTP method
     pub method: Option<String>,
+    /// Adaptive authentication risk score (0.0 - 1.0) of the session
+    #[serde(default)]
+    pub risk_score: f32,
+    /// Why the records are being accessed, as declared by the client
+    #[serde(default)]
+    pub purpose_of_use: Option<PurposeOfUse>,
+    /// Confidentiality labels on the resource, as recorded by the system
+    /// rather than asserted by the client
+    #[serde(default)]
+    pub security_labels: Vec<ConfidentialityLabel>,
+    /// Whether the patient consented under 42 CFR Part 2 to their substance
+    /// use records reaching the requester for the declared purpose
+    #[serde(default)]
+    pub part2_consent: bool,
 }
 
 /// Location context for geographic and facility-based authorization
@@ -135,9 +149,10 @@ pub struct WorkflowContext {
 }
 
 /// Urgency levels for clinical situations
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub enum UrgencyLevel {
     /// Routine, non-urgent access
+    #[default]
     Routine,
     /// Urgent but not critical
     Urgent,
@@ -194,6 +209,37 @@ pub enum WorkflowPriority {
     Critical,
 }
 
+/// Why patient data is accessed, for HIPAA minimum-necessary review
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PurposeOfUse {
+    /// Care of the patient
+    Treatment,
+    /// Billing and claims
+    Payment,
+    /// Healthcare operations such as quality review
+    Operations,
+    /// Research, which needs approval
+    Research,
+    /// Emergency treatment
+    Emergency,
+}
+
+/// Sensitivity of a record that restricts who may see it, carried as an
+/// HL7 v3 ActCode in FHIR `meta.security`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidentialityLabel {
+    /// Celebrity or other very important person
+    Vip,
+    /// Psychiatric disorder information
+    Psychiatric,
+    /// HIV/AIDS information
+    Hiv,
+    /// Substance use disorder information, protected by 42 CFR Part 2
+    SubstanceAbuse,
+}
+
 impl Default for RequestContext {
     fn default() -> Self {
         Self {
@@ -208,6 +254,10 @@ impl Default for RequestContext {
             headers: HashMap::new(),
             endpoint: None,
             method: None,
+            risk_score: 0.0,
+            purpose_of_use: None,
+            security_labels: Vec::new(),
+            part2_consent: false,
         }
     }
 }
@@ -224,6 +274,12 @@ impl RequestContext {
         self
     }
     
+    /// Add session risk score
+    pub fn with_risk_score(mut self, risk_score: f32) -> Self {
+        self.risk_score = risk_score.clamp(0.0, 1.0);
+        self
+    }
+    
     /// Add IP address
     pub fn with_ip(mut self, ip: String) -> Self {
         self.ip_address = Some(ip);
@@ -278,9 +334,33 @@ impl RequestContext {
         self
     }
     
+    /// Set the purpose of use
+    pub fn with_purpose_of_use(mut self, purpose: PurposeOfUse) -> Self {
+        self.purpose_of_use = Some(purpose);
+        self
+    }
+    
+    /// Add confidentiality labels of the resource
+    pub fn with_security_labels(mut self, labels: impl IntoIterator<Item = ConfidentialityLabel>) -> Self {
+        for label in labels {
+            if !self.security_labels.contains(&label) {
+                self.security_labels.push(label);
+            }
+        }
+        self.security_labels.sort();
+        self
+    }
+    
+    /// The declared purpose of use; declared emergencies are emergency
+    /// treatment when no purpose was given
+    pub fn purpose(&self) -> Option<PurposeOfUse> {
+        self.purpose_of_use
+            .or_else(|| self.is_emergency().then_some(PurposeOfUse::Emergency))
+    }
+    
     /// Check if this is an emergency situation
     pub fn is_emergency(&self) -> bool {
-        self.emergency.as_ref().map_or(false, |e| e.is_emergency)
+        self.emergency.as_ref().is_some_and(|e| e.is_emergency)
     }
     
     /// Get the urgency level
@@ -290,15 +370,32 @@ impl RequestContext {
             .unwrap_or(UrgencyLevel::Routine)
     }
     
+    /// Raise the urgency of a request about `patient_id` to `urgency_level`
+    /// when it is higher than the request's own, noting `source` in the
+    /// audit trail. Returns whether the urgency changed.
+    pub fn raise_urgency(&mut self, patient_id: Uuid, urgency_level: UrgencyLevel, source: &str) -> bool {
+        if urgency_level <= self.get_urgency_level() {
+            return false;
+        }
+        let clinical = self.clinical.get_or_insert_with(|| ClinicalContext::new().with_patient(patient_id));
+        clinical.patient_id.get_or_insert(patient_id);
+        clinical.urgency_level = urgency_level.clone();
+        self.audit_trail.push(format!(
+            "Urgency raised to {} for patient {} by {}",
+            urgency_level.as_str(), patient_id, source
+        ));
+        true
+    }
+    
     /// Check if this is a remote access request
     pub fn is_remote_access(&self) -> bool {
-        self.location.as_ref().map_or(false, |l| l.is_remote)
+        self.location.as_ref().is_some_and(|l| l.is_remote)
     }
     
     /// Check if this is after hours access
     pub fn is_after_hours(&self) -> bool {
         let hour = self.timestamp.hour();
-        hour < 8 || hour > 18 // Simple after-hours check (8 AM - 6 PM)
+        !(8..=18).contains(&hour) // Simple after-hours check (8 AM - 6 PM)
     }
     
     /// Check if this is weekend access
@@ -307,8 +404,21 @@ impl RequestContext {
         weekday == chrono::Weekday::Sat || weekday == chrono::Weekday::Sun
     }
     
-    /// Get security level based on connection and location
+    /// Get security level based on connection and location, lowered by
+    /// the session risk score
     pub fn get_security_level(&self) -> SecurityLevel {
+        let level = self.get_network_security_level();
+        if self.risk_score >= 0.7 {
+            SecurityLevel::Low
+        } else if self.risk_score >= 0.4 && matches!(level, SecurityLevel::High | SecurityLevel::Maximum) {
+            SecurityLevel::Medium
+        } else {
+            level
+        }
+    }
+    
+    /// Get security level based on connection and location only
+    pub fn get_network_security_level(&self) -> SecurityLevel {
         if let Some(location) = &self.location {
             if let Some(conn_info) = &location.connection_info {
                 conn_info.security_level.clone()
@@ -352,9 +462,112 @@ impl RequestContext {
     }
 }
 
-impl Default for UrgencyLevel {
-    fn default() -> Self {
-        UrgencyLevel::Routine
+impl UrgencyLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UrgencyLevel::Routine => "routine",
+            UrgencyLevel::Urgent => "urgent",
+            UrgencyLevel::Emergency => "emergency",
+            UrgencyLevel::Critical => "critical",
+        }
+    }
+    
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "routine" => Some(UrgencyLevel::Routine),
+            "urgent" => Some(UrgencyLevel::Urgent),
+            "emergency" => Some(UrgencyLevel::Emergency),
+            "critical" => Some(UrgencyLevel::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl PurposeOfUse {
+    /// HL7 v3 PurposeOfUse code
+    pub fn code(&self) -> &'static str {
+        match self {
+            PurposeOfUse::Treatment => "TREAT",
+            PurposeOfUse::Payment => "HPAYMT",
+            PurposeOfUse::Operations => "HOPERAT",
+            PurposeOfUse::Research => "HRESCH",
+            PurposeOfUse::Emergency => "ETREAT",
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PurposeOfUse::Treatment => "treatment",
+            PurposeOfUse::Payment => "payment",
+            PurposeOfUse::Operations => "operations",
+            PurposeOfUse::Research => "research",
+            PurposeOfUse::Emergency => "emergency",
+        }
+    }
+    
+    /// Purpose from its name or HL7 code, in any case
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        [
+            PurposeOfUse::Treatment,
+            PurposeOfUse::Payment,
+            PurposeOfUse::Operations,
+            PurposeOfUse::Research,
+            PurposeOfUse::Emergency,
+        ]
+        .into_iter()
+        .find(|purpose| s.eq_ignore_ascii_case(purpose.as_str()) || s.eq_ignore_ascii_case(purpose.code()))
+    }
+}
+
+impl ConfidentialityLabel {
+    pub const ALL: [ConfidentialityLabel; 4] = [
+        ConfidentialityLabel::Vip,
+        ConfidentialityLabel::Psychiatric,
+        ConfidentialityLabel::Hiv,
+        ConfidentialityLabel::SubstanceAbuse,
+    ];
+    
+    /// HL7 v3 ActCode sensitivity code
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfidentialityLabel::Vip => "CEL",
+            ConfidentialityLabel::Psychiatric => "PSY",
+            ConfidentialityLabel::Hiv => "HIV",
+            ConfidentialityLabel::SubstanceAbuse => "ETH",
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfidentialityLabel::Vip => "vip",
+            ConfidentialityLabel::Psychiatric => "psychiatric",
+            ConfidentialityLabel::Hiv => "hiv",
+            ConfidentialityLabel::SubstanceAbuse => "substance_abuse",
+        }
+    }
+    
+    /// HL7 display name of the code
+    pub fn display(&self) -> &'static str {
+        match self {
+            ConfidentialityLabel::Vip => "celebrity information sensitivity",
+            ConfidentialityLabel::Psychiatric => "psychiatry disorder information sensitivity",
+            ConfidentialityLabel::Hiv => "HIV/AIDS information sensitivity",
+            ConfidentialityLabel::SubstanceAbuse => "substance abuse information sensitivity",
+        }
+    }
+    
+    /// Label from its HL7 code
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|label| label.code() == code)
+    }
+    
+    /// Label from its name or HL7 code, in any case
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        Self::ALL
+            .into_iter()
+            .find(|label| s.eq_ignore_ascii_case(label.as_str()) || s.eq_ignore_ascii_case(label.code()))
     }
 }
 
@@ -387,6 +600,12 @@ impl LocationContext {
     }
 }
 
+impl Default for ClinicalContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ClinicalContext {
     /// Create a new clinical context
     pub fn new() -> Self {
@@ -0,0 +1,385 @@
+// src/modules/authorization/memory_storage.rs
+//! In-memory authorization storage
+//!
+//! Keeps relationships, policies, audit entries and the changelog in process
+//! memory. Intended for tests and embedded deployments where no database is
+//! available; nothing survives a restart.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use tokio::sync::RwLock;
+
+use super::relations::{RelationshipTuple, Subject, Resource, HealthcareRelation};
+use super::policies::HealthcarePolicy;
+use super::audit::{AuditEntry, AccessDecision};
+use super::changelog::{RelationshipChange, ChangeOperation, Zookie};
+use super::error::AuthError;
+use super::storage::{AuthorizationStorage, RelationStorage, PolicyStorage, ChangelogStorage, PolicyUsageStats};
+
+#[derive(Default)]
+struct MemoryState {
+    relationships: Vec<RelationshipTuple>,
+    policies: HashMap<String, HealthcarePolicy>,
+    audit_entries: Vec<AuditEntry>,
+    changelog: Vec<RelationshipChange>,
+}
+
+impl MemoryState {
+    fn record_change(&mut self, operation: ChangeOperation, tuple: RelationshipTuple) {
+        let revision = self.changelog.len() as i64 + 1;
+        self.changelog.push(RelationshipChange {
+            revision,
+            zookie: Zookie::from_revision(revision),
+            operation,
+            tuple,
+            changed_at: Utc::now(),
+        });
+    }
+
+    fn active_relationships(&self) -> impl Iterator<Item = &RelationshipTuple> {
+        self.relationships.iter().filter(|tuple| !tuple.is_expired())
+    }
+}
+
+/// In-memory implementation of authorization storage
+#[derive(Default)]
+pub struct InMemoryAuthorizationStorage {
+    state: RwLock<MemoryState>,
+}
+
+impl InMemoryAuthorizationStorage {
+    /// Create an empty in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Relations that express membership of a department, organization or group
+    fn is_membership(relation: &HealthcareRelation) -> bool {
+        matches!(relation, HealthcareRelation::DepartmentMember | HealthcareRelation::DepartmentHead)
+    }
+
+    /// The resource that holds the members of a group-like subject
+    fn subject_as_resource(subject: &Subject) -> Option<Resource> {
+        match subject {
+            Subject::Department(id) => Some(Resource::Department(*id)),
+            Subject::Organization(id) => Some(Resource::Organization(*id)),
+            _ => None,
+        }
+    }
+
+    /// The subject form of a group-like resource
+    fn resource_as_subject(resource: &Resource) -> Option<Subject> {
+        match resource {
+            Resource::Department(id) => Some(Subject::Department(*id)),
+            Resource::Organization(id) => Some(Subject::Organization(*id)),
+            _ => None,
+        }
+    }
+
+    fn same_tuple(a: &RelationshipTuple, b: &RelationshipTuple) -> bool {
+        a.object == b.object && a.relation == b.relation && a.subject == b.subject
+    }
+}
+
+#[async_trait]
+impl AuthorizationStorage for InMemoryAuthorizationStorage {
+    async fn store_relationship(&self, tuple: &RelationshipTuple) -> Result<(), AuthError> {
+        let mut state = self.state.write().await;
+        if state.active_relationships().any(|existing| Self::same_tuple(existing, tuple)) {
+            return Err(AuthError::Validation(format!(
+                "Relationship already exists: {}",
+                tuple.to_string_key()
+            )));
+        }
+
+        state.relationships.push(tuple.clone());
+        state.record_change(ChangeOperation::Added, tuple.clone());
+        Ok(())
+    }
+
+    async fn remove_relationship(&self, tuple: &RelationshipTuple) -> Result<(), AuthError> {
+        let mut state = self.state.write().await;
+        let before = state.relationships.len();
+        state.relationships.retain(|existing| !Self::same_tuple(existing, tuple));
+
+        if state.relationships.len() != before {
+            state.record_change(ChangeOperation::Removed, tuple.clone());
+        }
+        Ok(())
+    }
+
+    async fn has_relationship(
+        &self,
+        object: &Resource,
+        relation: &HealthcareRelation,
+        subject: &Subject,
+    ) -> Result<bool, AuthError> {
+        let state = self.state.read().await;
+        let found = state.active_relationships().any(|tuple| {
+            &tuple.object == object && &tuple.relation == relation && &tuple.subject == subject
+        });
+        Ok(found)
+    }
+
+    async fn check_relationships_batch(
+        &self,
+        tuples: &[(Resource, HealthcareRelation, Subject)],
+    ) -> Result<Vec<bool>, AuthError> {
+        let state = self.state.read().await;
+        Ok(tuples
+            .iter()
+            .map(|(object, relation, subject)| {
+                state.active_relationships().any(|tuple| {
+                    &tuple.object == object && &tuple.relation == relation && &tuple.subject == subject
+                })
+            })
+            .collect())
+    }
+
+    async fn get_relationships_for_resource(
+        &self,
+        resource: &Resource,
+    ) -> Result<Vec<RelationshipTuple>, AuthError> {
+        let state = self.state.read().await;
+        Ok(state
+            .active_relationships()
+            .filter(|tuple| &tuple.object == resource)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_relationships_for_subject(
+        &self,
+        subject: &Subject,
+    ) -> Result<Vec<RelationshipTuple>, AuthError> {
+        let state = self.state.read().await;
+        Ok(state
+            .active_relationships()
+            .filter(|tuple| &tuple.subject == subject)
+            .cloned()
+            .collect())
+    }
+
+    async fn store_policy(&self, policy: &HealthcarePolicy) -> Result<(), AuthError> {
+        let mut state = self.state.write().await;
+        state.policies.insert(policy.id.clone(), policy.clone());
+        Ok(())
+    }
+
+    async fn get_policy(&self, policy_id: &str) -> Result<Option<HealthcarePolicy>, AuthError> {
+        let state = self.state.read().await;
+        Ok(state.policies.get(policy_id).cloned())
+    }
+
+    async fn get_active_policies(&self) -> Result<Vec<HealthcarePolicy>, AuthError> {
+        let state = self.state.read().await;
+        let mut policies: Vec<HealthcarePolicy> = state
+            .policies
+            .values()
+            .filter(|policy| policy.is_active)
+            .cloned()
+            .collect();
+        policies.sort_by_key(|policy| std::cmp::Reverse(policy.priority));
+        Ok(policies)
+    }
+
+    async fn store_audit_entry(&self, entry: &AuditEntry) -> Result<(), AuthError> {
+        let mut state = self.state.write().await;
+        state.audit_entries.push(entry.clone());
+        Ok(())
+    }
+
+    async fn cleanup_expired_relationships(&self) -> Result<u64, AuthError> {
+        let mut state = self.state.write().await;
+        let (expired, active): (Vec<_>, Vec<_>) = std::mem::take(&mut state.relationships)
+            .into_iter()
+            .partition(|tuple| tuple.is_expired());
+        state.relationships = active;
+
+        let removed = expired.len() as u64;
+        for tuple in expired {
+            state.record_change(ChangeOperation::Removed, tuple);
+        }
+        Ok(removed)
+    }
+}
+
+#[async_trait]
+impl RelationStorage for InMemoryAuthorizationStorage {
+    async fn find_direct_relationships(
+        &self,
+        object: &Resource,
+        relation: &HealthcareRelation,
+    ) -> Result<Vec<Subject>, AuthError> {
+        let state = self.state.read().await;
+        Ok(state
+            .active_relationships()
+            .filter(|tuple| &tuple.object == object && &tuple.relation == relation)
+            .map(|tuple| tuple.subject.clone())
+            .collect())
+    }
+
+    async fn find_inherited_relationships(
+        &self,
+        object: &Resource,
+        relation: &HealthcareRelation,
+        max_depth: u8,
+    ) -> Result<Vec<Subject>, AuthError> {
+        let state = self.state.read().await;
+        let mut visited: HashSet<Subject> = HashSet::new();
+        let mut queue: VecDeque<(Subject, u8)> = VecDeque::new();
+        let mut inherited = Vec::new();
+
+        for tuple in state.active_relationships() {
+            if &tuple.object == object && &tuple.relation == relation && visited.insert(tuple.subject.clone()) {
+                queue.push_back((tuple.subject.clone(), 1));
+            }
+        }
+
+        // Walk membership edges downwards; `visited` prevents cycles
+        while let Some((subject, depth)) = queue.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+            let group = match Self::subject_as_resource(&subject) {
+                Some(group) => group,
+                None => continue,
+            };
+
+            for tuple in state.active_relationships() {
+                if tuple.object == group && Self::is_membership(&tuple.relation) && visited.insert(tuple.subject.clone()) {
+                    inherited.push(tuple.subject.clone());
+                    queue.push_back((tuple.subject.clone(), depth + 1));
+                }
+            }
+        }
+
+        Ok(inherited)
+    }
+
+    async fn get_subject_hierarchy(&self, subject: &Subject, max_depth: u8) -> Result<Vec<Subject>, AuthError> {
+        let state = self.state.read().await;
+        let mut visited: HashSet<Subject> = HashSet::from([subject.clone()]);
+        let mut queue: VecDeque<(Subject, u8)> = VecDeque::from([(subject.clone(), 0)]);
+        let mut hierarchy = Vec::new();
+
+        // Walk membership edges upwards; `visited` prevents cycles
+        while let Some((member, depth)) = queue.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+
+            for tuple in state.active_relationships() {
+                if tuple.subject != member || !Self::is_membership(&tuple.relation) {
+                    continue;
+                }
+                if let Some(group) = Self::resource_as_subject(&tuple.object) {
+                    if visited.insert(group.clone()) {
+                        hierarchy.push(group.clone());
+                        queue.push_back((group, depth + 1));
+                    }
+                }
+            }
+        }
+
+        Ok(hierarchy)
+    }
+}
+
+#[async_trait]
+impl PolicyStorage for InMemoryAuthorizationStorage {
+    async fn get_policies_by_type(&self, policy_type: &str) -> Result<Vec<HealthcarePolicy>, AuthError> {
+        let state = self.state.read().await;
+        Ok(state
+            .policies
+            .values()
+            .filter(|policy| {
+                serde_json::to_value(&policy.policy_type)
+                    .ok()
+                    .and_then(|value| value.as_str().map(|name| name.eq_ignore_ascii_case(policy_type)))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn update_policy_status(&self, policy_id: &str, is_active: bool) -> Result<(), AuthError> {
+        let mut state = self.state.write().await;
+        let policy = state.policies.get_mut(policy_id).ok_or(AuthError::ResourceNotFound)?;
+        policy.is_active = is_active;
+        policy.updated_at = Utc::now();
+        Ok(())
+    }
+
+    async fn get_policy_usage_stats(&self, policy_id: &str) -> Result<PolicyUsageStats, AuthError> {
+        let state = self.state.read().await;
+        let entries: Vec<&AuditEntry> = state
+            .audit_entries
+            .iter()
+            .filter(|entry| entry.reasons.iter().any(|reason| reason == policy_id))
+            .collect();
+
+        Ok(PolicyUsageStats {
+            policy_id: policy_id.to_string(),
+            total_evaluations: entries.len() as i64,
+            allow_decisions: entries.iter().filter(|e| matches!(e.decision, AccessDecision::Allow)).count() as i64,
+            deny_decisions: entries.iter().filter(|e| matches!(e.decision, AccessDecision::Deny)).count() as i64,
+            last_used: entries.iter().map(|e| e.timestamp).max(),
+        })
+    }
+}
+
+#[async_trait]
+impl ChangelogStorage for InMemoryAuthorizationStorage {
+    async fn read_changes(&self, since_revision: i64, limit: i64) -> Result<Vec<RelationshipChange>, AuthError> {
+        let state = self.state.read().await;
+        Ok(state
+            .changelog
+            .iter()
+            .filter(|change| change.revision > since_revision)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn current_revision(&self) -> Result<i64, AuthError> {
+        let state = self.state.read().await;
+        Ok(state.changelog.len() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_department_members_inherit_access() {
+        let storage = InMemoryAuthorizationStorage::new();
+        let patient = Resource::Patient(Uuid::new_v4());
+        let department = Uuid::new_v4();
+        let nurse = Uuid::new_v4();
+
+        storage.store_relationship(&RelationshipTuple::new(
+            patient.clone(),
+            HealthcareRelation::CareTeamMember,
+            Subject::Department(department),
+        )).await.unwrap();
+        storage.store_relationship(&RelationshipTuple::new(
+            Resource::Department(department),
+            HealthcareRelation::DepartmentMember,
+            Subject::User(nurse),
+        )).await.unwrap();
+
+        let inherited = storage
+            .find_inherited_relationships(&patient, &HealthcareRelation::CareTeamMember, 10)
+            .await
+            .unwrap();
+        assert_eq!(inherited, vec![Subject::User(nurse)]);
+
+        let hierarchy = storage.get_subject_hierarchy(&Subject::User(nurse), 10).await.unwrap();
+        assert_eq!(hierarchy, vec![Subject::Department(department)]);
+        assert_eq!(storage.current_revision().await.unwrap(), 2);
+    }
+}
@@ -9,7 +9,10 @@
 //! - Healthcare-specific context handling
 //! - Comprehensive audit logging
 //! - Emergency access management
+//! - Purpose-of-use enforcement on patient data access
+//! - Confidentiality labels restricting sensitive records to elevated relations
 //! - HIPAA/GDPR compliance features
+//! - Access checks over REST for clients enforcing decisions themselves
 
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
@@ -18,18 +21,54 @@ pub mod error;
 pub mod relations;
 pub mod healthcare_context;
 pub mod policies;
+pub mod external_policy;
 pub mod storage;
+pub mod memory_storage;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_storage;
 pub mod audit;
 pub mod engine;
+pub mod changelog;
+pub mod consistency;
 pub mod authorization_sql;
+pub mod controller;
 
 pub use error::*;
 pub use relations::*;
 pub use healthcare_context::*;
 pub use policies::*;
+pub use external_policy::*;
 pub use storage::*;
+pub use memory_storage::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_storage::*;
 pub use audit::*;
 pub use engine::*;
+pub use changelog::*;
+pub use consistency::*;
+pub use controller::AuthorizationController;
+
+/// Storage backend used for relationships, policies and audit entries
+#[derive(Debug, Clone, Default)]
+pub enum StorageBackend {
+    /// Shared PostgreSQL database (server deployments)
+    #[default]
+    Postgres,
+    /// Local SQLite database (desktop deployments without Postgres)
+    Sqlite { database_url: String },
+    /// Process memory (tests and embedded use)
+    InMemory,
+}
+
+/// Engine evaluating authorization policies
+#[derive(Debug, Clone, Default)]
+pub enum PolicyBackend {
+    /// The built-in healthcare policies
+    #[default]
+    Builtin,
+    /// An OPA server or Cedar authorization service
+    External(ExternalPolicyConfig),
+}
 
 /// Authorization configuration
 #[derive(Debug, Clone)]
@@ -43,6 +82,14 @@ pub struct AuthorizationConfig {
     pub max_cache_size: usize,
     pub enable_emergency_access: bool,
     pub max_relation_depth: u8,
+    pub watch_poll_interval_ms: u64,
+    pub watch_batch_size: i64,
+    pub consistency_wait_ms: u64,
+    pub storage_backend: StorageBackend,
+    pub policy_backend: PolicyBackend,
+    /// Actions that require MFA re-verification within `step_up_max_age_secs`
+    pub step_up_actions: Vec<Action>,
+    pub step_up_max_age_secs: i64,
 }
 
 impl Default for AuthorizationConfig {
@@ -57,7 +104,82 @@ impl Default for AuthorizationConfig {
             max_cache_size: 1000,
             enable_emergency_access: true,
             max_relation_depth: 10,
+            watch_poll_interval_ms: 1000,
+            watch_batch_size: 500,
+            consistency_wait_ms: 2000,
+            storage_backend: StorageBackend::default(),
+            policy_backend: PolicyBackend::default(),
+            step_up_actions: vec![
+                Action::BreakGlass,
+                Action::ExportData,
+                Action::ManageRoles,
+                Action::ManagePermissions,
+                Action::RestoreData,
+            ],
+            step_up_max_age_secs: 300,
+        }
+    }
+}
+
+impl AuthorizationConfig {
+    /// Configuration from the environment. `AUTHZ_STORAGE` selects the
+    /// storage backend: `postgres` (the default), `sqlite` at
+    /// `AUTHZ_SQLITE_URL`, or `memory`. `AUTHZ_POLICY_ENGINE` delegates
+    /// policies to `opa` or `cedar` at `AUTHZ_POLICY_ENDPOINT`, with
+    /// `AUTHZ_POLICY_DECISION_PATH`, `AUTHZ_POLICY_TOKEN`,
+    /// `AUTHZ_POLICY_TIMEOUT_MS` and `AUTHZ_POLICY_FAIL_CLOSED`.
+    pub fn from_env() -> Self {
+        let storage_backend = match std::env::var("AUTHZ_STORAGE").as_deref() {
+            Ok("sqlite") => StorageBackend::Sqlite {
+                database_url: std::env::var("AUTHZ_SQLITE_URL")
+                    .unwrap_or_else(|_| "sqlite://authorization.db".to_string()),
+            },
+            Ok("memory") => StorageBackend::InMemory,
+            Ok("postgres") | Err(_) => StorageBackend::Postgres,
+            Ok(other) => {
+                tracing::warn!("Unknown authorization storage {}, using PostgreSQL", other);
+                StorageBackend::Postgres
+            }
+        };
+
+        Self {
+            storage_backend,
+            policy_backend: Self::policy_backend_from_env(),
+            ..Self::default()
+        }
+    }
+
+    fn policy_backend_from_env() -> PolicyBackend {
+        let engine = std::env::var("AUTHZ_POLICY_ENGINE").unwrap_or_default();
+        if engine.is_empty() || engine == "builtin" {
+            return PolicyBackend::Builtin;
+        }
+        let Ok(endpoint) = std::env::var("AUTHZ_POLICY_ENDPOINT") else {
+            tracing::warn!("No AUTHZ_POLICY_ENDPOINT for the {} policy engine, using built-in policies", engine);
+            return PolicyBackend::Builtin;
+        };
+
+        let mut config = match engine.as_str() {
+            "opa" => ExternalPolicyConfig::opa(
+                endpoint,
+                std::env::var("AUTHZ_POLICY_DECISION_PATH").unwrap_or_else(|_| "hims/authz/decision".to_string()),
+            ),
+            "cedar" => ExternalPolicyConfig::cedar(endpoint),
+            other => {
+                tracing::warn!("Unknown policy engine {}, using built-in policies", other);
+                return PolicyBackend::Builtin;
+            }
+        };
+        if let Ok(token) = std::env::var("AUTHZ_POLICY_TOKEN") {
+            config = config.with_auth_token(token);
+        }
+        if let Some(timeout_ms) = std::env::var("AUTHZ_POLICY_TIMEOUT_MS").ok().and_then(|ms| ms.parse().ok()) {
+            config.timeout_ms = timeout_ms;
+        }
+        if let Some(fail_closed) = std::env::var("AUTHZ_POLICY_FAIL_CLOSED").ok().and_then(|value| value.parse().ok()) {
+            config.fail_closed = fail_closed;
         }
+        PolicyBackend::External(config)
     }
 }
 
@@ -72,6 +194,8 @@ pub struct SessionContext {
     pub location_id: Option<Uuid>,
     pub shift_id: Option<Uuid>,
     pub mfa_verified: bool,
+    /// When MFA was last completed in this session, used for step-up checks
+    pub mfa_verified_at: Option<DateTime<Utc>>,
     pub risk_score: f32,
 }
 
@@ -84,4 +208,5 @@ pub struct AuthorizationRequest {
     pub context: RequestContext,
     pub session: SessionContext,
     pub request_id: Option<String>,
+    pub consistency: Consistency,
 }
\ No newline at end of file
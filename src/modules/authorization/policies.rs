@@ -6,13 +6,14 @@
 //! time, location, and other factors.
 
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc, Timelike, Datelike, Weekday};
+use chrono::{DateTime, Utc, Datelike};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use std::collections::HashMap;
 
-use super::relations::{Action, Resource, Subject};
-use super::healthcare_context::{RequestContext, UrgencyLevel, EmergencyType, SecurityLevel};
+use super::relations::{Action, HealthcareRelation, Resource, Subject};
+use super::healthcare_context::{RequestContext, UrgencyLevel, EmergencyType, SecurityLevel, PurposeOfUse,
+    ConfidentialityLabel};
 
 /// A healthcare policy that defines authorization rules
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +72,8 @@ pub enum PolicyType {
     ResourceSpecific,
     /// Time-based policies
     TimeBased,
+    /// Purpose-of-use policies
+    PurposeOfUse,
     /// Custom policy type
     Custom(String),
 }
@@ -91,6 +94,8 @@ pub enum PolicyCondition {
     RemoteAccess,
     SecureConnection,
     MinimumSecurityLevel(SecurityLevel),
+    /// Session risk score must not exceed the given value (0.0 - 1.0)
+    MaximumRiskScore(f32),
     
     // Context-based conditions
     EmergencyDeclared,
@@ -98,6 +103,12 @@ pub enum PolicyCondition {
     PatientConsent,
     ClinicalContext(String),
     BreakGlassActivated,
+    /// The declared purpose of use is one of these
+    PurposeOfUse(Vec<PurposeOfUse>),
+    /// No purpose of use was declared
+    MissingPurposeOfUse,
+    /// No 42 CFR Part 2 consent covers the requester and purpose
+    MissingPart2Consent,
     
     // Role and relationship-based
     RequireRole(String),
@@ -111,6 +122,12 @@ pub enum PolicyCondition {
     ResourceOwnership,
     DataClassification(String),
     PatientRelated,
+    /// The resource holds patient data
+    PatientData,
+    /// The resource carries any of these confidentiality labels
+    SecurityLabel(Vec<ConfidentialityLabel>),
+    /// The action discloses data outside the system, such as an export
+    Disclosure,
     
     // Audit and compliance
     AuditTrailRequired,
@@ -146,6 +163,8 @@ pub enum PolicyEffect {
     TimeLimit(u64), // seconds
     /// Apply restrictions
     Restrict(Vec<String>),
+    /// Allow only subjects holding one of these relations
+    RequireRelations(Vec<HealthcareRelation>),
 }
 
 /// Result of policy evaluation
@@ -201,6 +220,13 @@ pub trait PolicyEngine: Send + Sync {
     
     /// List all active policies
     async fn list_active_policies(&self) -> Result<Vec<HealthcarePolicy>>;
+    
+    /// Whether decisions depend on the resource only through its type, so a
+    /// batch may reuse one decision for every resource of that type. Engines
+    /// that may decide on resource IDs must leave this false.
+    fn type_scoped(&self) -> bool {
+        false
+    }
 }
 
 /// Default implementation of the policy engine
@@ -357,6 +383,85 @@ impl HimsPolicyEngine {
                 updated_at: Utc::now(),
                 metadata: HashMap::new(),
             },
+            
+            // Purpose of use requirement
+            HealthcarePolicy {
+                id: "purpose-of-use-required".to_string(),
+                name: "Purpose of Use Required".to_string(),
+                description: "Deny patient data access that does not declare its purpose of use".to_string(),
+                policy_type: PolicyType::PurposeOfUse,
+                conditions: vec![
+                    PolicyCondition::PatientData,
+                    PolicyCondition::MissingPurposeOfUse,
+                ],
+                effect: PolicyEffect::Deny,
+                priority: 95,
+                is_active: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                metadata: HashMap::new(),
+            },
+            
+            // Research access approval
+            HealthcarePolicy {
+                id: "research-purpose-approval".to_string(),
+                name: "Research Access Approval".to_string(),
+                description: "Require approval for patient data accessed for research".to_string(),
+                policy_type: PolicyType::PurposeOfUse,
+                conditions: vec![
+                    PolicyCondition::PatientData,
+                    PolicyCondition::PurposeOfUse(vec![PurposeOfUse::Research]),
+                ],
+                effect: PolicyEffect::RequireApproval,
+                priority: 92,
+                is_active: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                metadata: HashMap::new(),
+            },
+            
+            // Labelled sensitive records are limited to the patient's clinicians
+            HealthcarePolicy {
+                id: "sensitive-record-elevated-access".to_string(),
+                name: "Sensitive Record Access".to_string(),
+                description: "Limit VIP, psychiatric, HIV and substance use records to treating clinicians \
+                    unless break-glass is invoked".to_string(),
+                policy_type: PolicyType::DataProtection,
+                conditions: vec![
+                    PolicyCondition::SecurityLabel(ConfidentialityLabel::ALL.to_vec()),
+                ],
+                effect: PolicyEffect::RequireRelations(vec![
+                    HealthcareRelation::PrimaryPhysician,
+                    HealthcareRelation::TreatingPhysician,
+                    HealthcareRelation::ConsultingPhysician,
+                    HealthcareRelation::AttendingNurse,
+                ]),
+                priority: 94,
+                is_active: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                metadata: HashMap::new(),
+            },
+            
+            // 42 CFR Part 2: substance use records leave only with the patient's consent
+            HealthcarePolicy {
+                id: "part2-disclosure-consent".to_string(),
+                name: "42 CFR Part 2 Disclosure Consent".to_string(),
+                description: "Block exports of substance use disorder records without a Part 2 consent \
+                    covering the recipient and purpose".to_string(),
+                policy_type: PolicyType::PatientConsent,
+                conditions: vec![
+                    PolicyCondition::SecurityLabel(vec![ConfidentialityLabel::SubstanceAbuse]),
+                    PolicyCondition::Disclosure,
+                    PolicyCondition::MissingPart2Consent,
+                ],
+                effect: PolicyEffect::Deny,
+                priority: 96,
+                is_active: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                metadata: HashMap::new(),
+            },
         ]
     }
     
@@ -364,6 +469,8 @@ impl HimsPolicyEngine {
     async fn evaluate_condition(
         &self,
         condition: &PolicyCondition,
+        action: &Action,
+        resource: &Resource,
         context: &RequestContext,
     ) -> Result<bool> {
         match condition {
@@ -412,6 +519,10 @@ impl HimsPolicyEngine {
                 Ok(Self::security_level_value(&current_level) >= Self::security_level_value(required_level))
             },
             
+            PolicyCondition::MaximumRiskScore(max_score) => {
+                Ok(context.risk_score <= *max_score)
+            },
+            
             PolicyCondition::RequireLocation(allowed_locations) => {
                 if let Some(location) = &context.location {
                     Ok(allowed_locations.contains(&location.hospital_id.to_string()))
@@ -449,7 +560,7 @@ impl HimsPolicyEngine {
             
             PolicyCondition::BreakGlassActivated => {
                 if let Some(emergency) = &context.emergency {
-                    Ok(emergency.emergency_type.as_ref().map_or(false, |t| {
+                    Ok(emergency.emergency_type.as_ref().is_some_and(|t| {
                         matches!(t, EmergencyType::BreakGlass)
                     }))
                 } else {
@@ -457,6 +568,30 @@ impl HimsPolicyEngine {
                 }
             },
             
+            PolicyCondition::PurposeOfUse(allowed) => {
+                Ok(context.purpose().is_some_and(|purpose| allowed.contains(&purpose)))
+            },
+            
+            PolicyCondition::MissingPurposeOfUse => {
+                Ok(context.purpose().is_none())
+            },
+            
+            PolicyCondition::PatientData => {
+                Ok(resource.is_patient_data())
+            },
+            
+            PolicyCondition::SecurityLabel(labels) => {
+                Ok(context.security_labels.iter().any(|label| labels.contains(label)))
+            },
+            
+            PolicyCondition::Disclosure => {
+                Ok(matches!(action, Action::ExportData))
+            },
+            
+            PolicyCondition::MissingPart2Consent => {
+                Ok(!context.part2_consent)
+            },
+            
             // For unimplemented conditions, default to true
             _ => Ok(true),
         }
@@ -476,7 +611,7 @@ impl HimsPolicyEngine {
     fn combine_effects(effects: Vec<(PolicyEffect, String, i32)>) -> PolicyEffect {
         // Sort by priority (highest first)
         let mut sorted_effects = effects;
-        sorted_effects.sort_by(|a, b| b.2.cmp(&a.2));
+        sorted_effects.sort_by_key(|effect| std::cmp::Reverse(effect.2));
         
         // Apply the highest priority effect
         if let Some((effect, _, _)) = sorted_effects.first() {
@@ -492,8 +627,8 @@ impl PolicyEngine for HimsPolicyEngine {
     async fn evaluate_policies(
         &self,
         _subject: &Subject,
-        _action: &Action,
-        _resource: &Resource,
+        action: &Action,
+        resource: &Resource,
         context: &RequestContext,
     ) -> Result<PolicyDecision> {
         let mut applicable_policies = Vec::new();
@@ -511,7 +646,7 @@ impl PolicyEngine for HimsPolicyEngine {
             // Check if all conditions are met
             let mut all_conditions_met = true;
             for condition in &policy.conditions {
-                if !self.evaluate_condition(condition, context).await? {
+                if !self.evaluate_condition(condition, action, resource, context).await? {
                     all_conditions_met = false;
                     break;
                 }
@@ -536,9 +671,13 @@ impl PolicyEngine for HimsPolicyEngine {
                     PolicyEffect::Restrict(restriction_list) => {
                         restrictions.extend(restriction_list.clone());
                     },
+                    PolicyEffect::RequireRelations(relations) => {
+                        let relations: Vec<String> = relations.iter().map(|r| r.to_string()).collect();
+                        requirements.push(format!("One of these relationships required: {}", relations.join(", ")));
+                    },
                     PolicyEffect::Conditional(conditions) => {
                         for condition in conditions {
-                            if !self.evaluate_condition(condition, context).await? {
+                            if !self.evaluate_condition(condition, action, resource, context).await? {
                                 requirements.push(format!("Condition not met: {:?}", condition));
                             }
                         }
@@ -601,10 +740,114 @@ impl PolicyEngine for HimsPolicyEngine {
             .cloned()
             .collect())
     }
+    
+    fn type_scoped(&self) -> bool {
+        // Conditions only ask whether the resource holds patient data
+        true
+    }
 }
 
 impl Default for HimsPolicyEngine {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    /// A weekday morning, inside business hours
+    fn context() -> RequestContext {
+        RequestContext {
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 4, 10, 0, 0).unwrap(),
+            ..RequestContext::new()
+        }
+    }
+
+    async fn decide(resource: Resource, context: RequestContext) -> PolicyDecision {
+        HimsPolicyEngine::new()
+            .evaluate_policies(&Subject::User(Uuid::new_v4()), &Action::Read, &resource, &context)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_purpose_of_use() {
+        assert_eq!(PurposeOfUse::parse("treatment"), Some(PurposeOfUse::Treatment));
+        assert_eq!(PurposeOfUse::parse(" HPAYMT "), Some(PurposeOfUse::Payment));
+        assert_eq!(PurposeOfUse::parse("Research"), Some(PurposeOfUse::Research));
+        assert_eq!(PurposeOfUse::parse("etreat"), Some(PurposeOfUse::Emergency));
+        assert_eq!(PurposeOfUse::parse("marketing"), None);
+    }
+
+    #[tokio::test]
+    async fn test_purpose_of_use_policies() {
+        let patient = Resource::Patient(Uuid::new_v4());
+
+        let decision = decide(patient.clone(), context()).await;
+        assert!(matches!(decision.decision, PolicyEffect::Deny));
+        assert!(decision.applied_policies.contains(&"purpose-of-use-required".to_string()));
+
+        // With a purpose, access falls through to relationship checks
+        let decision = decide(patient.clone(), context().with_purpose_of_use(PurposeOfUse::Treatment)).await;
+        assert!(matches!(decision.decision, PolicyEffect::AuditOnly));
+
+        let decision = decide(patient, context().with_purpose_of_use(PurposeOfUse::Research)).await;
+        assert!(matches!(decision.decision, PolicyEffect::RequireApproval));
+
+        // Data that is not a patient's needs no purpose
+        let decision = decide(Resource::Department(Uuid::new_v4()), context()).await;
+        assert!(matches!(decision.decision, PolicyEffect::AuditOnly));
+    }
+
+    #[tokio::test]
+    async fn test_security_label_policy() {
+        let record = Resource::MedicalRecord(Uuid::new_v4());
+        let treatment = context().with_purpose_of_use(PurposeOfUse::Treatment);
+
+        let decision = decide(record.clone(), treatment.clone()).await;
+        assert!(matches!(decision.decision, PolicyEffect::AuditOnly));
+
+        let labelled = treatment.with_security_labels([ConfidentialityLabel::Psychiatric]);
+        let decision = decide(record, labelled).await;
+        let PolicyEffect::RequireRelations(relations) = decision.decision else {
+            panic!("labelled records need elevated relations, got {:?}", decision.decision);
+        };
+        assert!(relations.contains(&HealthcareRelation::TreatingPhysician));
+        assert!(!relations.contains(&HealthcareRelation::CareTeamMember));
+        assert!(decision.applied_policies.contains(&"sensitive-record-elevated-access".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_part2_disclosure_policy() {
+        let patient = Resource::Patient(Uuid::new_v4());
+        let labelled = context()
+            .with_purpose_of_use(PurposeOfUse::Treatment)
+            .with_security_labels([ConfidentialityLabel::SubstanceAbuse]);
+        let export = |context: RequestContext| {
+            let patient = patient.clone();
+            async move {
+                HimsPolicyEngine::new()
+                    .evaluate_policies(&Subject::User(Uuid::new_v4()), &Action::ExportData, &patient, &context)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let decision = export(labelled.clone()).await;
+        assert!(matches!(decision.decision, PolicyEffect::Deny));
+        assert!(decision.applied_policies.contains(&"part2-disclosure-consent".to_string()));
+
+        let consented = RequestContext {
+            part2_consent: true,
+            ..labelled.clone()
+        };
+        let decision = export(consented).await;
+        assert!(!decision.applied_policies.contains(&"part2-disclosure-consent".to_string()));
+
+        let decision = decide(patient.clone(), labelled).await;
+        assert!(!decision.applied_policies.contains(&"part2-disclosure-consent".to_string()));
+    }
+}
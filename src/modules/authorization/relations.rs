@@ -80,6 +80,7 @@ pub enum Action {
     ViewResults,
     ModifyTreatment,
     ApproveTest,
+    RecordDeviceData,
     
     // Administrative actions
     Schedule,
@@ -273,6 +274,21 @@ impl Display for Subject {
     }
 }
 
+impl Resource {
+    /// Whether the resource holds protected health information of a
+    /// patient, whose access needs a purpose of use
+    pub fn is_patient_data(&self) -> bool {
+        !matches!(
+            self,
+            Resource::Department(_)
+                | Resource::Organization(_)
+                | Resource::Report(_)
+                | Resource::ClinicalDecisionSupport(_)
+                | Resource::SystemConfig(_)
+        )
+    }
+}
+
 impl Display for Resource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -310,6 +326,7 @@ impl Display for Action {
             Action::ViewResults => write!(f, "view_results"),
             Action::ModifyTreatment => write!(f, "modify_treatment"),
             Action::ApproveTest => write!(f, "approve_test"),
+            Action::RecordDeviceData => write!(f, "record_device_data"),
             Action::Schedule => write!(f, "schedule"),
             Action::Cancel => write!(f, "cancel"),
             Action::Approve => write!(f, "approve"),
@@ -505,4 +522,79 @@ impl FromStr for HealthcareRelation {
             other => Ok(HealthcareRelation::Custom(other.to_string())),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Parse Resource from its `type:id` string form
+impl FromStr for Resource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, id) = s.split_once(':').ok_or_else(|| format!("Resource must be type:id, got {}", s))?;
+        if kind == "system_config" {
+            return Ok(Resource::SystemConfig(id.to_string()));
+        }
+        let uuid = Uuid::parse_str(id).map_err(|_| format!("Invalid resource ID: {}", id))?;
+        match kind {
+            "patient" => Ok(Resource::Patient(uuid)),
+            "medical_record" => Ok(Resource::MedicalRecord(uuid)),
+            "appointment" => Ok(Resource::Appointment(uuid)),
+            "department" => Ok(Resource::Department(uuid)),
+            "organization" => Ok(Resource::Organization(uuid)),
+            "prescription" => Ok(Resource::Prescription(uuid)),
+            "lab_result" => Ok(Resource::LabResult(uuid)),
+            "imaging_study" => Ok(Resource::ImagingStudy(uuid)),
+            "report" => Ok(Resource::Report(uuid)),
+            "billing" => Ok(Resource::Billing(uuid)),
+            "care_plan" => Ok(Resource::CarePlan(uuid)),
+            "encounter" => Ok(Resource::Encounter(uuid)),
+            "clinical_decision_support" => Ok(Resource::ClinicalDecisionSupport(uuid)),
+            "research_data" => Ok(Resource::ResearchData(uuid)),
+            other => Err(format!("Unknown resource type: {}", other)),
+        }
+    }
+}
+
+/// Parse Action from string
+impl FromStr for Action {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Action::Read),
+            "write" => Ok(Action::Write),
+            "create" => Ok(Action::Create),
+            "delete" => Ok(Action::Delete),
+            "search" => Ok(Action::Search),
+            "update" => Ok(Action::Update),
+            "prescribe" => Ok(Action::Prescribe),
+            "diagnose" => Ok(Action::Diagnose),
+            "order_test" => Ok(Action::OrderTest),
+            "view_results" => Ok(Action::ViewResults),
+            "modify_treatment" => Ok(Action::ModifyTreatment),
+            "approve_test" => Ok(Action::ApproveTest),
+            "record_device_data" => Ok(Action::RecordDeviceData),
+            "schedule" => Ok(Action::Schedule),
+            "cancel" => Ok(Action::Cancel),
+            "approve" => Ok(Action::Approve),
+            "reject" => Ok(Action::Reject),
+            "audit" => Ok(Action::Audit),
+            "configure" => Ok(Action::Configure),
+            "emergency_access" => Ok(Action::EmergencyAccess),
+            "break_glass" => Ok(Action::BreakGlass),
+            "generate_report" => Ok(Action::GenerateReport),
+            "export_data" => Ok(Action::ExportData),
+            "view_analytics" => Ok(Action::ViewAnalytics),
+            "view_billing" => Ok(Action::ViewBilling),
+            "process_payment" => Ok(Action::ProcessPayment),
+            "adjust_billing" => Ok(Action::AdjustBilling),
+            "research_access" => Ok(Action::ResearchAccess),
+            "deidentify" => Ok(Action::DeIdentify),
+            "manage_users" => Ok(Action::ManageUsers),
+            "manage_roles" => Ok(Action::ManageRoles),
+            "manage_permissions" => Ok(Action::ManagePermissions),
+            "backup_data" => Ok(Action::BackupData),
+            "restore_data" => Ok(Action::RestoreData),
+            other => Ok(Action::Custom(other.to_string())),
+        }
+    }
+}
@@ -0,0 +1,602 @@
+// src/modules/authorization/sqlite_storage.rs
+//! SQLite storage layer for authorization data
+//!
+//! Used by the desktop app, which runs without a PostgreSQL server. The schema
+//! mirrors the PostgreSQL tables and is created on first connect.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Executor;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::relations::{RelationshipTuple, Subject, Resource, HealthcareRelation};
+use super::policies::{HealthcarePolicy, PolicyType};
+use super::audit::AuditEntry;
+use super::changelog::{RelationshipChange, ChangeOperation, Zookie};
+use super::error::AuthError;
+use super::storage::{
+    AuthorizationStorage, RelationStorage, PolicyStorage, ChangelogStorage,
+    PolicyUsageStats, PostgresAuthorizationStorage as Parts,
+};
+use super::authorization_sql::sqlite as sql;
+
+type RelationRow = (String, String, String, String, String, String, Option<DateTime<Utc>>, Option<String>, DateTime<Utc>);
+type PolicyRow = (String, String, Option<String>, String, String, String, i32, bool, DateTime<Utc>, DateTime<Utc>, String);
+
+/// SQLite implementation of authorization storage
+pub struct SqliteAuthorizationStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteAuthorizationStorage {
+    /// Open (creating if needed) the database at `database_url` and ensure the schema exists
+    pub async fn connect(database_url: &str) -> Result<Self, AuthError> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await?;
+
+        let storage = Self { pool };
+        storage.initialize().await?;
+        Ok(storage)
+    }
+
+    /// Create a storage on an existing pool and ensure the schema exists
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self, AuthError> {
+        let storage = Self { pool };
+        storage.initialize().await?;
+        Ok(storage)
+    }
+
+    /// Create tables, indexes and changelog triggers
+    async fn initialize(&self) -> Result<(), AuthError> {
+        self.pool.execute(sql::CREATE_SCHEMA).await?;
+        Ok(())
+    }
+
+    fn row_to_tuple(row: RelationRow) -> Result<RelationshipTuple, AuthError> {
+        let (resource_type, resource_id, relation_str, subject_type, subject_id, metadata, expires_at, created_by, created_at) = row;
+
+        let relation = relation_str.parse().map_err(|_| {
+            AuthError::Storage(anyhow::anyhow!("Invalid relation type: {}", relation_str))
+        })?;
+        let metadata: HashMap<String, String> = serde_json::from_str(&metadata).unwrap_or_default();
+
+        Ok(RelationshipTuple {
+            object: Parts::parts_to_resource(&resource_type, &resource_id)?,
+            relation,
+            subject: Parts::parts_to_subject(&subject_type, &subject_id)?,
+            context: None,
+            expires_at,
+            created_by: created_by.map(|id| uuid::Uuid::parse_str(&id)).transpose()?,
+            created_at,
+            metadata,
+        })
+    }
+
+    /// Policy types are stored as their serde name (e.g. `RoleBased`)
+    fn policy_type_to_str(policy_type: &PolicyType) -> String {
+        match serde_json::to_value(policy_type) {
+            Ok(serde_json::Value::String(name)) => name,
+            Ok(value) => value.to_string(),
+            Err(_) => "Default".to_string(),
+        }
+    }
+
+    fn parse_policy_type(value: &str) -> PolicyType {
+        serde_json::from_value(serde_json::Value::String(value.to_string()))
+            .or_else(|_| serde_json::from_str(value))
+            .unwrap_or_default()
+    }
+
+    fn row_to_policy(row: PolicyRow) -> HealthcarePolicy {
+        let (id, name, description, policy_type, conditions, effect, priority, is_active, created_at, updated_at, metadata) = row;
+
+        HealthcarePolicy {
+            id,
+            name,
+            description: description.unwrap_or_default(),
+            policy_type: Self::parse_policy_type(&policy_type),
+            conditions: serde_json::from_str(&conditions).unwrap_or_default(),
+            effect: Parts::parse_effect(&effect),
+            priority,
+            is_active,
+            created_at,
+            updated_at,
+            metadata: serde_json::from_str(&metadata).unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait]
+impl AuthorizationStorage for SqliteAuthorizationStorage {
+    async fn store_relationship(&self, tuple: &RelationshipTuple) -> Result<(), AuthError> {
+        let (resource_type, resource_id) = Parts::resource_to_parts(&tuple.object);
+        let (subject_type, subject_id) = Parts::subject_to_parts(&tuple.subject);
+
+        sqlx::query(sql::INSERT_RELATIONSHIP)
+            .bind(&resource_type)
+            .bind(&resource_id)
+            .bind(tuple.relation.to_string())
+            .bind(&subject_type)
+            .bind(&subject_id)
+            .bind(tuple.created_by.map(|id| id.to_string()))
+            .bind(serde_json::to_string(&tuple.metadata).unwrap_or_else(|_| "{}".to_string()))
+            .bind(tuple.expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_relationship(&self, tuple: &RelationshipTuple) -> Result<(), AuthError> {
+        let (resource_type, resource_id) = Parts::resource_to_parts(&tuple.object);
+        let (subject_type, subject_id) = Parts::subject_to_parts(&tuple.subject);
+
+        sqlx::query(sql::REMOVE_RELATIONSHIP)
+            .bind(&resource_type)
+            .bind(&resource_id)
+            .bind(tuple.relation.to_string())
+            .bind(&subject_type)
+            .bind(&subject_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn has_relationship(
+        &self,
+        object: &Resource,
+        relation: &HealthcareRelation,
+        subject: &Subject,
+    ) -> Result<bool, AuthError> {
+        let (resource_type, resource_id) = Parts::resource_to_parts(object);
+        let (subject_type, subject_id) = Parts::subject_to_parts(subject);
+
+        let exists = sqlx::query_scalar::<_, bool>(sql::CHECK_RELATIONSHIP)
+            .bind(&resource_type)
+            .bind(&resource_id)
+            .bind(relation.to_string())
+            .bind(&subject_type)
+            .bind(&subject_id)
+            .bind(Utc::now())
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(exists)
+    }
+
+    async fn check_relationships_batch(
+        &self,
+        tuples: &[(Resource, HealthcareRelation, Subject)],
+    ) -> Result<Vec<bool>, AuthError> {
+        // SQLite has no array parameters; reuse one connection for the whole batch
+        let mut conn = self.pool.acquire().await?;
+        let now = Utc::now();
+        let mut results = Vec::with_capacity(tuples.len());
+
+        for (object, relation, subject) in tuples {
+            let (resource_type, resource_id) = Parts::resource_to_parts(object);
+            let (subject_type, subject_id) = Parts::subject_to_parts(subject);
+
+            let exists = sqlx::query_scalar::<_, bool>(sql::CHECK_RELATIONSHIP)
+                .bind(&resource_type)
+                .bind(&resource_id)
+                .bind(relation.to_string())
+                .bind(&subject_type)
+                .bind(&subject_id)
+                .bind(now)
+                .fetch_one(&mut *conn)
+                .await?;
+            results.push(exists);
+        }
+
+        Ok(results)
+    }
+
+    async fn get_relationships_for_resource(
+        &self,
+        resource: &Resource,
+    ) -> Result<Vec<RelationshipTuple>, AuthError> {
+        let (resource_type, resource_id) = Parts::resource_to_parts(resource);
+
+        let rows = sqlx::query_as::<_, RelationRow>(sql::GET_RELATIONSHIPS_FOR_RESOURCE)
+            .bind(&resource_type)
+            .bind(&resource_id)
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(Self::row_to_tuple).collect()
+    }
+
+    async fn get_relationships_for_subject(
+        &self,
+        subject: &Subject,
+    ) -> Result<Vec<RelationshipTuple>, AuthError> {
+        let (subject_type, subject_id) = Parts::subject_to_parts(subject);
+
+        let rows = sqlx::query_as::<_, RelationRow>(sql::GET_RELATIONSHIPS_FOR_SUBJECT)
+            .bind(&subject_type)
+            .bind(&subject_id)
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(Self::row_to_tuple).collect()
+    }
+
+    async fn store_policy(&self, policy: &HealthcarePolicy) -> Result<(), AuthError> {
+        sqlx::query(sql::UPSERT_POLICY)
+            .bind(&policy.id)
+            .bind(&policy.name)
+            .bind(&policy.description)
+            .bind(Self::policy_type_to_str(&policy.policy_type))
+            .bind(serde_json::to_string(&policy.conditions).unwrap_or_else(|_| "[]".to_string()))
+            .bind(Parts::effect_to_str(&policy.effect))
+            .bind(policy.priority)
+            .bind(policy.is_active)
+            .bind(policy.created_at)
+            .bind(policy.updated_at)
+            .bind(serde_json::to_string(&policy.metadata).unwrap_or_else(|_| "{}".to_string()))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_policy(&self, policy_id: &str) -> Result<Option<HealthcarePolicy>, AuthError> {
+        let row = sqlx::query_as::<_, PolicyRow>(sql::GET_POLICY)
+            .bind(policy_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(Self::row_to_policy))
+    }
+
+    async fn get_active_policies(&self) -> Result<Vec<HealthcarePolicy>, AuthError> {
+        let rows = sqlx::query_as::<_, PolicyRow>(sql::GET_ACTIVE_POLICIES)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(Self::row_to_policy).collect())
+    }
+
+    async fn store_audit_entry(&self, entry: &AuditEntry) -> Result<(), AuthError> {
+        sqlx::query(sql::INSERT_AUDIT_ENTRY)
+            .bind(entry.id.to_string())
+            .bind(entry.user_id.map(|id| id.to_string()))
+            .bind(entry.action.to_string())
+            .bind(&entry.resource_namespace)
+            .bind(&entry.resource_id)
+            .bind(Parts::decision_to_str(&entry.decision))
+            .bind(serde_json::to_string(&entry.reasons).unwrap_or_else(|_| "[]".to_string()))
+            .bind(&entry.ip_address)
+            .bind(&entry.user_agent)
+            .bind(&entry.session_id)
+            .bind(serde_json::to_string(&entry.metadata).unwrap_or_else(|_| "{}".to_string()))
+            .bind(entry.timestamp)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn cleanup_expired_relationships(&self) -> Result<u64, AuthError> {
+        let result = sqlx::query(sql::CLEANUP_EXPIRED_RELATIONSHIPS)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl RelationStorage for SqliteAuthorizationStorage {
+    async fn find_direct_relationships(
+        &self,
+        object: &Resource,
+        relation: &HealthcareRelation,
+    ) -> Result<Vec<Subject>, AuthError> {
+        let (resource_type, resource_id) = Parts::resource_to_parts(object);
+
+        let rows = sqlx::query_as::<_, (String, String)>(sql::FIND_DIRECT_RELATIONSHIPS)
+            .bind(&resource_type)
+            .bind(&resource_id)
+            .bind(relation.to_string())
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|(subject_type, subject_id)| Parts::parts_to_subject(subject_type, subject_id))
+            .collect()
+    }
+
+    async fn find_inherited_relationships(
+        &self,
+        object: &Resource,
+        relation: &HealthcareRelation,
+        max_depth: u8,
+    ) -> Result<Vec<Subject>, AuthError> {
+        if max_depth == 0 {
+            return Ok(Vec::new());
+        }
+
+        let (resource_type, resource_id) = Parts::resource_to_parts(object);
+
+        let rows = sqlx::query_as::<_, (String, String)>(sql::FIND_INHERITED_RELATIONSHIPS)
+            .bind(&resource_type)
+            .bind(&resource_id)
+            .bind(relation.to_string())
+            .bind(max_depth as i32)
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|(subject_type, subject_id)| Parts::parts_to_subject(subject_type, subject_id))
+            .collect()
+    }
+
+    async fn get_subject_hierarchy(&self, subject: &Subject, max_depth: u8) -> Result<Vec<Subject>, AuthError> {
+        if max_depth == 0 {
+            return Ok(Vec::new());
+        }
+
+        let (subject_type, subject_id) = Parts::subject_to_parts(subject);
+
+        let rows = sqlx::query_as::<_, (String, String)>(sql::GET_SUBJECT_HIERARCHY)
+            .bind(&subject_type)
+            .bind(&subject_id)
+            .bind(max_depth as i32)
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|(group_type, group_id)| Parts::parts_to_subject(group_type, group_id))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl PolicyStorage for SqliteAuthorizationStorage {
+    async fn get_policies_by_type(&self, policy_type: &str) -> Result<Vec<HealthcarePolicy>, AuthError> {
+        let rows = sqlx::query_as::<_, PolicyRow>(sql::GET_POLICIES_BY_TYPE)
+            .bind(policy_type)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(Self::row_to_policy).collect())
+    }
+
+    async fn update_policy_status(&self, policy_id: &str, is_active: bool) -> Result<(), AuthError> {
+        sqlx::query(sql::UPDATE_POLICY_STATUS)
+            .bind(is_active)
+            .bind(Utc::now())
+            .bind(policy_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_policy_usage_stats(&self, policy_id: &str) -> Result<PolicyUsageStats, AuthError> {
+        let (total_evaluations, allow_decisions, deny_decisions, last_used) =
+            sqlx::query_as::<_, (i64, i64, i64, Option<DateTime<Utc>>)>(sql::GET_POLICY_USAGE_STATS)
+                .bind(policy_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(PolicyUsageStats {
+            policy_id: policy_id.to_string(),
+            total_evaluations,
+            allow_decisions,
+            deny_decisions,
+            last_used,
+        })
+    }
+}
+
+#[async_trait]
+impl ChangelogStorage for SqliteAuthorizationStorage {
+    async fn read_changes(&self, since_revision: i64, limit: i64) -> Result<Vec<RelationshipChange>, AuthError> {
+        let rows = sqlx::query_as::<_, (i64, String, String, String, String, String, String, Option<String>, DateTime<Utc>)>(
+            sql::GET_CHANGES_SINCE
+        )
+        .bind(since_revision)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut changes = Vec::new();
+        for (revision, operation, resource_type, resource_id, relation_str, subject_type, subject_id, changed_by, changed_at) in rows {
+            let relation = relation_str.parse().map_err(|_| {
+                AuthError::Storage(anyhow::anyhow!("Invalid relation type: {}", relation_str))
+            })?;
+
+            let mut tuple = RelationshipTuple::new(
+                Parts::parts_to_resource(&resource_type, &resource_id)?,
+                relation,
+                Parts::parts_to_subject(&subject_type, &subject_id)?,
+            );
+            tuple.created_by = changed_by.map(|id| uuid::Uuid::parse_str(&id)).transpose()?;
+            tuple.created_at = changed_at;
+
+            changes.push(RelationshipChange {
+                revision,
+                zookie: Zookie::from_revision(revision),
+                operation: operation.parse::<ChangeOperation>()?,
+                tuple,
+                changed_at,
+            });
+        }
+
+        Ok(changes)
+    }
+
+    async fn current_revision(&self) -> Result<i64, AuthError> {
+        let revision = sqlx::query_scalar::<_, i64>(sql::GET_CURRENT_REVISION)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(revision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    async fn storage() -> SqliteAuthorizationStorage {
+        SqliteAuthorizationStorage::connect("sqlite::memory:").await.unwrap()
+    }
+
+    async fn relate(
+        storage: &SqliteAuthorizationStorage,
+        object: Resource,
+        relation: HealthcareRelation,
+        subject: Subject,
+    ) {
+        storage.store_relationship(&RelationshipTuple::new(object, relation, subject)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_store_and_check_relationship() {
+        let storage = storage().await;
+        let patient = Resource::Patient(Uuid::new_v4());
+        let (doctor, clerk) = (Subject::User(Uuid::new_v4()), Subject::User(Uuid::new_v4()));
+
+        relate(&storage, patient.clone(), HealthcareRelation::PrimaryPhysician, doctor.clone()).await;
+        assert!(storage.has_relationship(&patient, &HealthcareRelation::PrimaryPhysician, &doctor).await.unwrap());
+        assert!(!storage.has_relationship(&patient, &HealthcareRelation::PrimaryPhysician, &clerk).await.unwrap());
+        assert!(!storage.has_relationship(&patient, &HealthcareRelation::AttendingNurse, &doctor).await.unwrap());
+
+        let batch = storage
+            .check_relationships_batch(&[
+                (patient.clone(), HealthcareRelation::PrimaryPhysician, doctor.clone()),
+                (patient.clone(), HealthcareRelation::PrimaryPhysician, clerk.clone()),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(batch, vec![true, false]);
+
+        let tuples = storage.get_relationships_for_resource(&patient).await.unwrap();
+        assert_eq!(tuples.len(), 1);
+        assert_eq!(tuples[0].subject, doctor);
+        assert_eq!(storage.get_relationships_for_subject(&doctor).await.unwrap()[0].object, patient);
+
+        storage
+            .remove_relationship(&RelationshipTuple::new(
+                patient.clone(),
+                HealthcareRelation::PrimaryPhysician,
+                doctor.clone(),
+            ))
+            .await
+            .unwrap();
+        assert!(!storage.has_relationship(&patient, &HealthcareRelation::PrimaryPhysician, &doctor).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_inherited_relationships() {
+        let storage = storage().await;
+        let patient = Resource::Patient(Uuid::new_v4());
+        let (organization, department, nurse) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        // The organization cares for the patient; the nurse is in one of its departments
+        relate(&storage, patient.clone(), HealthcareRelation::CareTeamMember, Subject::Organization(organization))
+            .await;
+        relate(
+            &storage,
+            Resource::Organization(organization),
+            HealthcareRelation::DepartmentMember,
+            Subject::Department(department),
+        ).await;
+        relate(&storage, Resource::Department(department), HealthcareRelation::DepartmentMember, Subject::User(nurse))
+            .await;
+
+        let inherited = storage
+            .find_inherited_relationships(&patient, &HealthcareRelation::CareTeamMember, 10)
+            .await
+            .unwrap();
+        assert_eq!(inherited.len(), 2);
+        assert!(inherited.contains(&Subject::Department(department)));
+        assert!(inherited.contains(&Subject::User(nurse)));
+
+        // One level only reaches the department
+        let shallow = storage
+            .find_inherited_relationships(&patient, &HealthcareRelation::CareTeamMember, 2)
+            .await
+            .unwrap();
+        assert_eq!(shallow, vec![Subject::Department(department)]);
+
+        let hierarchy = storage.get_subject_hierarchy(&Subject::User(nurse), 10).await.unwrap();
+        assert_eq!(hierarchy.len(), 2);
+        assert!(hierarchy.contains(&Subject::Department(department)));
+        assert!(hierarchy.contains(&Subject::Organization(organization)));
+    }
+
+    #[tokio::test]
+    async fn test_cyclic_hierarchy() {
+        let storage = storage().await;
+        let patient = Resource::Patient(Uuid::new_v4());
+        let (ward, clinic, nurse) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        // Two departments each listed as a member of the other
+        relate(&storage, Resource::Department(ward), HealthcareRelation::DepartmentMember, Subject::Department(clinic))
+            .await;
+        relate(&storage, Resource::Department(clinic), HealthcareRelation::DepartmentMember, Subject::Department(ward))
+            .await;
+        relate(&storage, Resource::Department(ward), HealthcareRelation::DepartmentMember, Subject::User(nurse)).await;
+        relate(&storage, patient.clone(), HealthcareRelation::CareTeamMember, Subject::Department(ward)).await;
+
+        let inherited = storage
+            .find_inherited_relationships(&patient, &HealthcareRelation::CareTeamMember, 10)
+            .await
+            .unwrap();
+        assert_eq!(inherited.len(), 2);
+        assert!(inherited.contains(&Subject::Department(clinic)));
+        assert!(inherited.contains(&Subject::User(nurse)));
+
+        let hierarchy = storage.get_subject_hierarchy(&Subject::User(nurse), 10).await.unwrap();
+        assert_eq!(hierarchy.len(), 2);
+        assert!(hierarchy.contains(&Subject::Department(ward)));
+        assert!(hierarchy.contains(&Subject::Department(clinic)));
+    }
+
+    #[tokio::test]
+    async fn test_changelog() {
+        let storage = storage().await;
+        let patient = Resource::Patient(Uuid::new_v4());
+        let (doctor, nurse) = (Subject::User(Uuid::new_v4()), Subject::User(Uuid::new_v4()));
+        assert_eq!(storage.current_revision().await.unwrap(), 0);
+
+        relate(&storage, patient.clone(), HealthcareRelation::PrimaryPhysician, doctor.clone()).await;
+        relate(&storage, patient.clone(), HealthcareRelation::AttendingNurse, nurse.clone()).await;
+        storage
+            .remove_relationship(&RelationshipTuple::new(patient.clone(), HealthcareRelation::AttendingNurse, nurse))
+            .await
+            .unwrap();
+        assert_eq!(storage.current_revision().await.unwrap(), 3);
+
+        let changes = storage.read_changes(0, 10).await.unwrap();
+        let operations: Vec<ChangeOperation> = changes.iter().map(|change| change.operation.clone()).collect();
+        assert_eq!(operations, vec![ChangeOperation::Added, ChangeOperation::Added, ChangeOperation::Removed]);
+        assert_eq!(changes[0].tuple.subject, doctor);
+        assert_eq!(changes[2].zookie.revision().unwrap(), 3);
+
+        let since = storage.read_changes(1, 1).await.unwrap();
+        assert_eq!(since.len(), 1);
+        assert_eq!(since[0].revision, 2);
+    }
+}
@@ -5,17 +5,21 @@
 //! authorization relationships, policies, and audit logs.
 
 use async_trait::async_trait;
-use sqlx::{PgPool, Row};
+use sqlx::PgPool;
 use anyhow::Result;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use super::relations::{RelationshipTuple, Subject, Resource, HealthcareRelation};
 use super::policies::HealthcarePolicy;
 use super::audit::AuditEntry;
+use super::changelog::{RelationshipChange, ChangeOperation, Zookie};
 use super::error::AuthError;
 use super::authorization_sql;
+use super::memory_storage::InMemoryAuthorizationStorage;
+use super::{AuthorizationConfig, StorageBackend};
 
 /// Trait for authorization data storage
 #[async_trait]
@@ -34,6 +38,12 @@ pub trait AuthorizationStorage: Send + Sync {
         subject: &Subject,
     ) -> Result<bool, AuthError>;
     
+    /// Check many relationships in a single query, returning one result per tuple in order
+    async fn check_relationships_batch(
+        &self,
+        tuples: &[(Resource, HealthcareRelation, Subject)],
+    ) -> Result<Vec<bool>, AuthError>;
+    
     /// Get all relationships for a resource
     async fn get_relationships_for_resource(
         &self,
@@ -80,8 +90,8 @@ pub trait RelationStorage: Send + Sync {
         max_depth: u8,
     ) -> Result<Vec<Subject>, AuthError>;
     
-    /// Get relationship hierarchy for a subject
-    async fn get_subject_hierarchy(&self, subject: &Subject) -> Result<Vec<Subject>, AuthError>;
+    /// Get the departments, organizations and groups a subject belongs to
+    async fn get_subject_hierarchy(&self, subject: &Subject, max_depth: u8) -> Result<Vec<Subject>, AuthError>;
 }
 
 /// Trait for policy-specific storage operations
@@ -97,6 +107,51 @@ pub trait PolicyStorage: Send + Sync {
     async fn get_policy_usage_stats(&self, policy_id: &str) -> Result<PolicyUsageStats, AuthError>;
 }
 
+/// Trait for reading the relationship changelog
+#[async_trait]
+pub trait ChangelogStorage: Send + Sync {
+    /// Read up to `limit` changes recorded after `since_revision`, oldest first
+    async fn read_changes(&self, since_revision: i64, limit: i64) -> Result<Vec<RelationshipChange>, AuthError>;
+    
+    /// Get the latest changelog revision
+    async fn current_revision(&self) -> Result<i64, AuthError>;
+}
+
+/// Everything the authorization engine needs from a storage backend
+pub trait AuthorizationBackend: AuthorizationStorage + RelationStorage + PolicyStorage + ChangelogStorage {}
+
+impl<T> AuthorizationBackend for T
+where
+    T: AuthorizationStorage + RelationStorage + PolicyStorage + ChangelogStorage,
+{}
+
+/// Create the storage backend selected in the authorization configuration.
+///
+/// `pool` is only required for the PostgreSQL backend.
+pub async fn create_storage(
+    config: &AuthorizationConfig,
+    pool: Option<PgPool>,
+) -> Result<Arc<dyn AuthorizationBackend>, AuthError> {
+    match &config.storage_backend {
+        StorageBackend::Postgres => {
+            let pool = pool.ok_or_else(|| {
+                AuthError::Configuration("PostgreSQL authorization storage requires a database pool".to_string())
+            })?;
+            Ok(Arc::new(PostgresAuthorizationStorage::new(pool)))
+        },
+        StorageBackend::InMemory => Ok(Arc::new(InMemoryAuthorizationStorage::new())),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite { database_url } => {
+            let storage = super::sqlite_storage::SqliteAuthorizationStorage::connect(database_url).await?;
+            Ok(Arc::new(storage))
+        },
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite { .. } => Err(AuthError::Configuration(
+            "SQLite authorization storage requires the `sqlite` feature".to_string()
+        )),
+    }
+}
+
 /// PostgreSQL implementation of authorization storage
 pub struct PostgresAuthorizationStorage {
     pool: PgPool,
@@ -119,7 +174,7 @@ impl PostgresAuthorizationStorage {
     }
     
     /// Convert Resource enum to namespace and ID
-    fn resource_to_parts(resource: &Resource) -> (String, String) {
+    pub(super) fn resource_to_parts(resource: &Resource) -> (String, String) {
         match resource {
             Resource::Patient(id) => ("patient".to_string(), id.to_string()),
             Resource::MedicalRecord(id) => ("medical_record".to_string(), id.to_string()),
@@ -140,7 +195,7 @@ impl PostgresAuthorizationStorage {
     }
     
     /// Convert Subject enum to namespace and ID
-    fn subject_to_parts(subject: &Subject) -> (String, String) {
+    pub(super) fn subject_to_parts(subject: &Subject) -> (String, String) {
         match subject {
             Subject::User(id) => ("user".to_string(), id.to_string()),
             Subject::Role(role) => ("role".to_string(), role.clone()),
@@ -152,7 +207,7 @@ impl PostgresAuthorizationStorage {
     }
     
     /// Convert namespace and ID back to Resource
-    fn parts_to_resource(namespace: &str, id: &str) -> Result<Resource, AuthError> {
+    pub(super) fn parts_to_resource(namespace: &str, id: &str) -> Result<Resource, AuthError> {
         match namespace {
             "patient" => Ok(Resource::Patient(Uuid::parse_str(id)?)),
             "medical_record" => Ok(Resource::MedicalRecord(Uuid::parse_str(id)?)),
@@ -174,7 +229,7 @@ impl PostgresAuthorizationStorage {
     }
     
     /// Convert namespace and ID back to Subject
-    fn parts_to_subject(namespace: &str, id: &str) -> Result<Subject, AuthError> {
+    pub(super) fn parts_to_subject(namespace: &str, id: &str) -> Result<Subject, AuthError> {
         match namespace {
             "user" => Ok(Subject::User(Uuid::parse_str(id)?)),
             "role" => Ok(Subject::Role(id.to_string())),
@@ -186,8 +241,36 @@ impl PostgresAuthorizationStorage {
         }
     }
     
+    /// Convert a policy effect to its database representation
+    pub(super) fn effect_to_str(effect: &super::policies::PolicyEffect) -> &'static str {
+        match effect {
+            super::policies::PolicyEffect::Allow => "allow",
+            super::policies::PolicyEffect::Deny => "deny",
+            super::policies::PolicyEffect::RequireApproval => "require_approval",
+            super::policies::PolicyEffect::RequireSecondFactor => "require_second_factor",
+            super::policies::PolicyEffect::AuditOnly => "audit_only",
+            super::policies::PolicyEffect::TimeLimit(_) => "time_limit",
+            super::policies::PolicyEffect::Restrict(_) => "restrict",
+            super::policies::PolicyEffect::Conditional(_) => "conditional",
+            super::policies::PolicyEffect::RequireRelations(_) => "require_relations",
+        }
+    }
+    
+    /// Convert an access decision to its database representation
+    pub(super) fn decision_to_str(decision: &super::audit::AccessDecision) -> &'static str {
+        match decision {
+            super::audit::AccessDecision::Allow => "allow",
+            super::audit::AccessDecision::Deny => "deny",
+            super::audit::AccessDecision::RequireApproval => "require_approval",
+            super::audit::AccessDecision::RequireMFA => "require_mfa",
+            super::audit::AccessDecision::AllowWithRestrictions => "allow_with_restrictions",
+            super::audit::AccessDecision::EmergencyAccess => "emergency_access",
+            super::audit::AccessDecision::BreakGlassAccess => "emergency_access", // Map to emergency_access
+        }
+    }
+    
     /// Parse effect string from database
-    fn parse_effect(effect_str: &str) -> super::policies::PolicyEffect {
+    pub(super) fn parse_effect(effect_str: &str) -> super::policies::PolicyEffect {
         match effect_str {
             "allow" => super::policies::PolicyEffect::Allow,
             "deny" => super::policies::PolicyEffect::Deny,
@@ -197,6 +280,7 @@ impl PostgresAuthorizationStorage {
             "time_limit" => super::policies::PolicyEffect::TimeLimit(3600), // default 1 hour
             "restrict" => super::policies::PolicyEffect::Restrict(vec![]),
             "conditional" => super::policies::PolicyEffect::Conditional(vec![]),
+            "require_relations" => super::policies::PolicyEffect::RequireRelations(vec![]),
             _ => super::policies::PolicyEffect::Deny,
         }
     }
@@ -212,13 +296,13 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
         
         sqlx::query(authorization_sql::relationships::INSERT_RELATIONSHIP)
             .bind(&resource_type)
-            .bind(&resource_id_uuid)
-            .bind(&tuple.relation.to_string())
+            .bind(resource_id_uuid)
+            .bind(tuple.relation.to_string())
             .bind(&subject_type)
-            .bind(&subject_id_uuid)
-            .bind(&tuple.created_by)
-            .bind(&serde_json::to_value(&tuple.metadata).unwrap_or_default())
-            .bind(&tuple.expires_at)
+            .bind(subject_id_uuid)
+            .bind(tuple.created_by)
+            .bind(serde_json::to_value(&tuple.metadata).unwrap_or_default())
+            .bind(tuple.expires_at)
             .execute(&self.pool)
             .await?;
         
@@ -233,10 +317,10 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
         
         sqlx::query(authorization_sql::relationships::REMOVE_RELATIONSHIP)
             .bind(&resource_type)
-            .bind(&resource_id_uuid)
-            .bind(&tuple.relation.to_string())
+            .bind(resource_id_uuid)
+            .bind(tuple.relation.to_string())
             .bind(&subject_type)
-            .bind(&subject_id_uuid)
+            .bind(subject_id_uuid)
             .execute(&self.pool)
             .await?;
         
@@ -256,16 +340,60 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
         
         let result = sqlx::query_scalar::<_, bool>(authorization_sql::relationships::CHECK_RELATIONSHIP)
             .bind(&resource_type)
-            .bind(&resource_id_uuid)
-            .bind(&relation.to_string())
+            .bind(resource_id_uuid)
+            .bind(relation.to_string())
             .bind(&subject_type)
-            .bind(&subject_id_uuid)
+            .bind(subject_id_uuid)
             .fetch_one(&self.pool)
             .await?;
         
         Ok(result)
     }
     
+    async fn check_relationships_batch(
+        &self,
+        tuples: &[(Resource, HealthcareRelation, Subject)],
+    ) -> Result<Vec<bool>, AuthError> {
+        if tuples.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let mut resource_types = Vec::with_capacity(tuples.len());
+        let mut resource_ids = Vec::with_capacity(tuples.len());
+        let mut relations = Vec::with_capacity(tuples.len());
+        let mut subject_types = Vec::with_capacity(tuples.len());
+        let mut subject_ids = Vec::with_capacity(tuples.len());
+        
+        for (object, relation, subject) in tuples {
+            let (resource_type, resource_id) = Self::resource_to_parts(object);
+            let (subject_type, subject_id) = Self::subject_to_parts(subject);
+            resource_types.push(resource_type);
+            resource_ids.push(Uuid::parse_str(&resource_id)?);
+            relations.push(relation.to_string());
+            subject_types.push(subject_type);
+            subject_ids.push(Uuid::parse_str(&subject_id)?);
+        }
+        
+        let found = sqlx::query_scalar::<_, i64>(authorization_sql::relationships::CHECK_RELATIONSHIPS_BATCH)
+            .bind(&resource_types)
+            .bind(&resource_ids)
+            .bind(&relations)
+            .bind(&subject_types)
+            .bind(&subject_ids)
+            .fetch_all(&self.pool)
+            .await?;
+        
+        // Ordinality is 1-based
+        let mut results = vec![false; tuples.len()];
+        for ordinal in found {
+            if let Some(slot) = results.get_mut((ordinal - 1) as usize) {
+                *slot = true;
+            }
+        }
+        
+        Ok(results)
+    }
+    
     async fn get_relationships_for_resource(
         &self,
         resource: &Resource,
@@ -284,7 +412,7 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
             "#
         )
         .bind(&resource_type)
-        .bind(&resource_id_uuid)
+        .bind(resource_id_uuid)
         .fetch_all(&self.pool)
         .await?;
         
@@ -333,7 +461,7 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
             "#
         )
         .bind(&subject_type)
-        .bind(&subject_id_uuid)
+        .bind(subject_id_uuid)
         .fetch_all(&self.pool)
         .await?;
         
@@ -366,16 +494,7 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
     
     async fn store_policy(&self, policy: &HealthcarePolicy) -> Result<(), AuthError> {
         // Extract policy_type and effect as strings for the database
-        let effect_str = match &policy.effect {
-            super::policies::PolicyEffect::Allow => "allow",
-            super::policies::PolicyEffect::Deny => "deny",
-            super::policies::PolicyEffect::RequireApproval => "require_approval",
-            super::policies::PolicyEffect::RequireSecondFactor => "require_second_factor",
-            super::policies::PolicyEffect::AuditOnly => "audit_only",
-            super::policies::PolicyEffect::TimeLimit(_) => "time_limit",
-            super::policies::PolicyEffect::Restrict(_) => "restrict",
-            super::policies::PolicyEffect::Conditional(_) => "conditional",
-        };
+        let effect_str = Self::effect_to_str(&policy.effect);
         
         let policy_id_uuid = Uuid::parse_str(&policy.id)?;
         
@@ -400,16 +519,16 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
                 metadata = EXCLUDED.metadata
             "#
         )
-        .bind(&policy_id_uuid)
+        .bind(policy_id_uuid)
         .bind(&policy.name)
         .bind(&policy.description)
-        .bind(&serde_json::to_value(&policy.conditions).unwrap_or_default())
+        .bind(serde_json::to_value(&policy.conditions).unwrap_or_default())
         .bind(effect_str)
-        .bind(&policy.priority)
-        .bind(&policy.is_active)
-        .bind(&system_user_id)
-        .bind(&system_user_id)
-        .bind(&serde_json::to_value(&policy.metadata).unwrap_or_default())
+        .bind(policy.priority)
+        .bind(policy.is_active)
+        .bind(system_user_id)
+        .bind(system_user_id)
+        .bind(serde_json::to_value(&policy.metadata).unwrap_or_default())
         .execute(&self.pool)
         .await?;
         
@@ -427,7 +546,7 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
             WHERE id = $1
             "#
         )
-        .bind(&policy_id_uuid)
+        .bind(policy_id_uuid)
         .fetch_optional(&self.pool)
         .await?;
         
@@ -492,15 +611,7 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
     }
     
     async fn store_audit_entry(&self, entry: &AuditEntry) -> Result<(), AuthError> {
-        let decision_str = match &entry.decision {
-            super::audit::AccessDecision::Allow => "allow",
-            super::audit::AccessDecision::Deny => "deny",
-            super::audit::AccessDecision::RequireApproval => "require_approval",
-            super::audit::AccessDecision::RequireMFA => "require_mfa",
-            super::audit::AccessDecision::AllowWithRestrictions => "allow_with_restrictions",
-            super::audit::AccessDecision::EmergencyAccess => "emergency_access",
-            super::audit::AccessDecision::BreakGlassAccess => "emergency_access", // Map to emergency_access
-        };
+        let decision_str = Self::decision_to_str(&entry.decision);
         
         let resource_id_uuid = Uuid::parse_str(&entry.resource_id)?;
         
@@ -522,17 +633,17 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
             VALUES ($1, $2, $3, $4, $5, $6, $7::inet, $8, $9, $10, $11)
             "#
         )
-        .bind(&entry.user_id)
-        .bind(&entry.action.to_string())
+        .bind(entry.user_id)
+        .bind(entry.action.to_string())
         .bind(&entry.resource_namespace)
-        .bind(&resource_id_uuid)
+        .bind(resource_id_uuid)
         .bind(decision_str)
         .bind(&reasons_array[..])
         .bind(&ip_str)
         .bind(&entry.user_agent)
         .bind(&entry.session_id)
-        .bind(&serde_json::to_value(&entry.request_context).unwrap_or_default())
-        .bind(&serde_json::to_value(&entry.metadata).unwrap_or_default())
+        .bind(serde_json::to_value(&entry.request_context).unwrap_or_default())
+        .bind(serde_json::to_value(&entry.metadata).unwrap_or_default())
         .execute(&self.pool)
         .await?;
         
@@ -540,16 +651,9 @@ impl AuthorizationStorage for PostgresAuthorizationStorage {
     }
     
     async fn cleanup_expired_relationships(&self) -> Result<u64, AuthError> {
-        let result = sqlx::query(
-            r#"
-            UPDATE authorization_relations 
-            SET is_active = false 
-            WHERE expires_at IS NOT NULL AND expires_at <= NOW()
-            AND is_active = true
-            "#
-        )
-        .execute(&self.pool)
-        .await?;
+        let result = sqlx::query(authorization_sql::relationships::CLEANUP_EXPIRED_RELATIONSHIPS)
+            .execute(&self.pool)
+            .await?;
         
         Ok(result.rows_affected())
     }
@@ -566,8 +670,8 @@ impl RelationStorage for PostgresAuthorizationStorage {
         
         let rows = sqlx::query_as::<_, (String, uuid::Uuid, String, chrono::DateTime<chrono::Utc>, serde_json::Value)>(authorization_sql::relationships::FIND_RELATIONSHIPS_FOR_RESOURCE)
             .bind(&resource_type)
-            .bind(&Uuid::parse_str(&resource_id)?)
-            .bind(&relation.to_string())
+            .bind(Uuid::parse_str(&resource_id)?)
+            .bind(relation.to_string())
             .fetch_all(&self.pool)
             .await?;
         
@@ -582,27 +686,60 @@ impl RelationStorage for PostgresAuthorizationStorage {
     
     async fn find_inherited_relationships(
         &self,
-        _object: &Resource,
-        _relation: &HealthcareRelation,
-        _max_depth: u8,
+        object: &Resource,
+        relation: &HealthcareRelation,
+        max_depth: u8,
     ) -> Result<Vec<Subject>, AuthError> {
-        // This would implement recursive relationship resolution
-        // For now, return empty to avoid complexity
-        Ok(Vec::new())
+        if max_depth == 0 {
+            return Ok(Vec::new());
+        }
+        
+        let (resource_type, resource_id) = Self::resource_to_parts(object);
+        
+        let rows = sqlx::query_as::<_, (String, Uuid)>(authorization_sql::relationships::FIND_INHERITED_RELATIONSHIPS)
+            .bind(&resource_type)
+            .bind(Uuid::parse_str(&resource_id)?)
+            .bind(relation.to_string())
+            .bind(max_depth as i32)
+            .fetch_all(&self.pool)
+            .await?;
+        
+        let mut subjects = Vec::new();
+        for (subject_type, subject_id) in rows {
+            subjects.push(Self::parts_to_subject(&subject_type, &subject_id.to_string())?);
+        }
+        
+        Ok(subjects)
     }
     
-    async fn get_subject_hierarchy(&self, _subject: &Subject) -> Result<Vec<Subject>, AuthError> {
-        // This would implement subject hierarchy traversal
-        // For now, return empty to avoid complexity
-        Ok(Vec::new())
+    async fn get_subject_hierarchy(&self, subject: &Subject, max_depth: u8) -> Result<Vec<Subject>, AuthError> {
+        if max_depth == 0 {
+            return Ok(Vec::new());
+        }
+        
+        let (subject_type, subject_id) = Self::subject_to_parts(subject);
+        
+        let rows = sqlx::query_as::<_, (String, Uuid)>(authorization_sql::relationships::GET_SUBJECT_HIERARCHY)
+            .bind(&subject_type)
+            .bind(Uuid::parse_str(&subject_id)?)
+            .bind(max_depth as i32)
+            .fetch_all(&self.pool)
+            .await?;
+        
+        let mut hierarchy = Vec::new();
+        for (group_type, group_id) in rows {
+            hierarchy.push(Self::parts_to_subject(&group_type, &group_id.to_string())?);
+        }
+        
+        Ok(hierarchy)
     }
 }
 
 #[async_trait]
 impl PolicyStorage for PostgresAuthorizationStorage {
-    async fn get_policies_by_type(&self, policy_type: &str) -> Result<Vec<HealthcarePolicy>, AuthError> {
+    async fn get_policies_by_type(&self, _policy_type: &str) -> Result<Vec<HealthcarePolicy>, AuthError> {
         // For now, return active policies and filter by name/description containing the type
-        let rows = sqlx::query(authorization_sql::policies::GET_ACTIVE_POLICIES)
+        let _rows = sqlx::query(authorization_sql::policies::GET_ACTIVE_POLICIES)
             .fetch_all(&self.pool)
             .await?;
 
@@ -621,8 +758,8 @@ impl PolicyStorage for PostgresAuthorizationStorage {
             WHERE id = $2
             "#
         )
-        .bind(&is_active)
-        .bind(&policy_id_uuid)
+        .bind(is_active)
+        .bind(policy_id_uuid)
         .execute(&self.pool)
         .await?;
         
@@ -653,4 +790,49 @@ impl PolicyStorage for PostgresAuthorizationStorage {
             last_used: row.3,
         })
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl ChangelogStorage for PostgresAuthorizationStorage {
+    async fn read_changes(&self, since_revision: i64, limit: i64) -> Result<Vec<RelationshipChange>, AuthError> {
+        let rows = sqlx::query_as::<_, (i64, String, String, Uuid, String, String, Uuid, Option<Uuid>, DateTime<Utc>)>(
+            authorization_sql::changelog::GET_CHANGES_SINCE
+        )
+        .bind(since_revision)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut changes = Vec::new();
+        for (revision, operation_str, resource_type, resource_id, relation_str, subject_type, subject_id, changed_by, changed_at) in rows {
+            let object = Self::parts_to_resource(&resource_type, &resource_id.to_string())?;
+            let subject = Self::parts_to_subject(&subject_type, &subject_id.to_string())?;
+            let relation = relation_str.parse().map_err(|_| {
+                AuthError::Storage(anyhow::anyhow!("Invalid relation type: {}", relation_str))
+            })?;
+            let operation: ChangeOperation = operation_str.parse()?;
+            
+            let mut tuple = RelationshipTuple::new(object, relation, subject);
+            tuple.created_by = changed_by;
+            tuple.created_at = changed_at;
+            
+            changes.push(RelationshipChange {
+                revision,
+                zookie: Zookie::from_revision(revision),
+                operation,
+                tuple,
+                changed_at,
+            });
+        }
+        
+        Ok(changes)
+    }
+    
+    async fn current_revision(&self) -> Result<i64, AuthError> {
+        let revision = sqlx::query_scalar::<_, i64>(authorization_sql::changelog::GET_CURRENT_REVISION)
+            .fetch_one(&self.pool)
+            .await?;
+        
+        Ok(revision)
+    }
+}
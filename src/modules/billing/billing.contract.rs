@@ -0,0 +1,525 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::modules::billing::billing_service::{BillingCodeSystem, Charge};
+
+/// A payer's fee schedule under a contract, for one of its plans or, with no
+/// plan, for all of them
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeSchedule {
+    pub id: Uuid,
+    /// The payer's identifier on claims
+    pub payer_id: String,
+    pub payer_name: String,
+    pub plan: Option<String>,
+    pub name: String,
+    pub effective_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
+    /// Share of billed charges, in hundredths of a percent, paid for codes
+    /// the schedule has no rate for. `None` if they are not paid.
+    pub percent_of_charges: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FeeSchedule {
+    /// Whether the schedule prices services on a date
+    pub fn in_effect(&self, on: NaiveDate) -> bool {
+        self.effective_date <= on && self.termination_date.is_none_or(|terminated| on <= terminated)
+    }
+}
+
+/// A fee schedule to add to a payer's contract
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeScheduleRequest {
+    pub payer_id: String,
+    pub payer_name: String,
+    pub plan: Option<String>,
+    pub name: String,
+    pub effective_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
+    pub percent_of_charges: Option<i32>,
+}
+
+impl FeeScheduleRequest {
+    /// Check the request's shape
+    pub fn check(&self) -> Result<(), String> {
+        if self.payer_id.trim().is_empty() || self.payer_name.trim().is_empty() || self.name.trim().is_empty() {
+            return Err("A fee schedule needs the payer's identifier and name, and its own name".to_string());
+        }
+        if self.termination_date.is_some_and(|terminated| terminated < self.effective_date) {
+            return Err("A fee schedule cannot end before it takes effect".to_string());
+        }
+        if self.percent_of_charges.is_some_and(|percent| !(0..=10_000).contains(&percent)) {
+            return Err("A share of charges is between 0 and 10000 hundredths of a percent".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A fee schedule's rate for a code, per unit, from its effective date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRate {
+    pub code_system: BillingCodeSystem,
+    pub code: String,
+    /// Modifier the rate is for, e.g. 26 for the professional component;
+    /// `None` for the code billed without one
+    pub modifier: Option<String>,
+    pub rate: i64,
+    pub effective_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
+}
+
+impl ScheduleRate {
+    pub fn in_effect(&self, on: NaiveDate) -> bool {
+        self.effective_date <= on && self.termination_date.is_none_or(|terminated| on <= terminated)
+    }
+}
+
+/// The fee schedule that prices a payer's services on a date: one for the
+/// plan over one for all the payer's plans, then the latest to take effect
+pub fn select_schedule<'a>(
+    schedules: &'a [FeeSchedule],
+    payer_id: &str,
+    plan: Option<&str>,
+    on: NaiveDate,
+) -> Option<&'a FeeSchedule> {
+    schedules
+        .iter()
+        .filter(|schedule| schedule.payer_id == payer_id && schedule.in_effect(on))
+        .filter(|schedule| schedule.plan.is_none() || schedule.plan.as_deref() == plan)
+        .max_by_key(|schedule| (schedule.plan.is_some(), schedule.effective_date))
+}
+
+/// A schedule's rate for a code on a date: one for a modifier the line
+/// carries over the code's own
+pub fn find_rate<'a>(
+    rates: &'a [ScheduleRate],
+    code: &str,
+    modifiers: &[String],
+    on: NaiveDate,
+) -> Option<&'a ScheduleRate> {
+    rates
+        .iter()
+        .filter(|rate| rate.code == code && rate.in_effect(on))
+        .filter(|rate| rate.modifier.as_ref().is_none_or(|modifier| modifiers.contains(modifier)))
+        .max_by_key(|rate| (rate.modifier.is_some(), rate.effective_date))
+}
+
+/// How a line's expected payment was priced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingBasis {
+    /// The fee schedule's rate for the code
+    FeeSchedule,
+    /// A share of the billed charge
+    PercentOfCharges,
+    /// No schedule or rate prices the line
+    Unpriced,
+}
+
+/// What a contract says a payer should allow for a charge. Amounts are in
+/// the smallest unit of the currency.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpectedPayment {
+    pub charge_id: Uuid,
+    pub fee_schedule_id: Option<Uuid>,
+    pub basis: PricingBasis,
+    pub billed: i64,
+    /// Allowed amount, the payer's and the patient's shares together;
+    /// `None` if the line is unpriced
+    pub expected: Option<i64>,
+    /// Billed less expected, written off under the contract
+    pub contractual_adjustment: Option<i64>,
+}
+
+/// The expected payment for a charge under a schedule: the rate times the
+/// units, or the schedule's share of the billed charge for codes it has no
+/// rate for, and never more than was billed
+pub fn expected_payment(schedule: Option<&FeeSchedule>, rates: &[ScheduleRate], charge: &Charge) -> ExpectedPayment {
+    let billed = charge.amount();
+    let priced = schedule.and_then(|schedule| {
+        match find_rate(rates, &charge.code, &charge.modifiers, charge.service_date) {
+            Some(rate) => Some((PricingBasis::FeeSchedule, rate.rate * i64::from(charge.units))),
+            None => schedule
+                .percent_of_charges
+                .map(|percent| (PricingBasis::PercentOfCharges, (billed * i64::from(percent) + 5_000) / 10_000)),
+        }
+    });
+    let (basis, expected) = match priced {
+        Some((basis, amount)) => (basis, Some(amount.min(billed))),
+        None => (PricingBasis::Unpriced, None),
+    };
+    ExpectedPayment {
+        charge_id: charge.id,
+        fee_schedule_id: schedule.map(|schedule| schedule.id),
+        basis,
+        billed,
+        expected,
+        contractual_adjustment: expected.map(|expected| billed - expected),
+    }
+}
+
+/// Claim adjustment group of an 835 adjustment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdjustmentGroup {
+    /// Contractual obligation, written off
+    #[serde(rename = "CO")]
+    ContractualObligation,
+    /// Patient responsibility, billed to the patient
+    #[serde(rename = "PR")]
+    PatientResponsibility,
+    #[serde(rename = "OA")]
+    OtherAdjustment,
+    #[serde(rename = "PI")]
+    PayerInitiated,
+    #[serde(rename = "CR")]
+    Correction,
+}
+
+/// An adjustment of a remittance line, as 835 CAS segments give it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemittanceAdjustment {
+    pub group: AdjustmentGroup,
+    /// Claim adjustment reason code, e.g. 45 for charges over the fee
+    /// schedule
+    pub reason_code: String,
+    pub amount: i64,
+}
+
+/// Sum of a line's adjustments in a group
+pub fn adjusted(adjustments: &[RemittanceAdjustment], group: AdjustmentGroup) -> i64 {
+    adjustments
+        .iter()
+        .filter(|adjustment| adjustment.group == group)
+        .map(|adjustment| adjustment.amount)
+        .sum()
+}
+
+/// A remittance line posted against a charge. Amounts are in the smallest
+/// unit of the currency; a reversal's are negative.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChargePayment {
+    pub id: Uuid,
+    pub charge_id: Uuid,
+    pub payer_id: String,
+    pub plan: Option<String>,
+    /// The payer's claim control number
+    pub payer_claim_id: Option<String>,
+    pub paid_date: NaiveDate,
+    pub paid: i64,
+    /// The line's group PR adjustments
+    pub patient_responsibility: i64,
+    pub adjustments: Vec<RemittanceAdjustment>,
+    pub posted_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A remittance line to post, from an 835 SVC segment and its CAS segments
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaymentRequest {
+    pub charge_id: Uuid,
+    pub payer_id: String,
+    pub plan: Option<String>,
+    pub payer_claim_id: Option<String>,
+    pub paid_date: NaiveDate,
+    pub paid: i64,
+    #[serde(default)]
+    pub adjustments: Vec<RemittanceAdjustment>,
+}
+
+impl PaymentRequest {
+    /// Check the request's shape
+    pub fn check(&self) -> Result<(), String> {
+        if self.payer_id.trim().is_empty() {
+            return Err("A payment needs the payer's identifier".to_string());
+        }
+        if self.adjustments.iter().any(|adjustment| adjustment.reason_code.trim().is_empty()) {
+            return Err("An adjustment needs its reason code".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How a payment compares with what the contract says
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VarianceStatus {
+    AsExpected,
+    Underpaid,
+    Overpaid,
+    /// The line is unpriced, so there is nothing to compare with
+    Unpriced,
+}
+
+impl VarianceStatus {
+    /// Status of an actual allowed amount against the expected one
+    pub fn of(expected: Option<i64>, actual: i64) -> Self {
+        match expected.map(|expected| actual.cmp(&expected)) {
+            None => VarianceStatus::Unpriced,
+            Some(std::cmp::Ordering::Equal) => VarianceStatus::AsExpected,
+            Some(std::cmp::Ordering::Less) => VarianceStatus::Underpaid,
+            Some(std::cmp::Ordering::Greater) => VarianceStatus::Overpaid,
+        }
+    }
+}
+
+/// What a payer allowed for a charge over all its remittance lines against
+/// what its contract says to expect
+#[derive(Debug, Clone, Serialize)]
+pub struct ChargeVariance {
+    pub charge_id: Uuid,
+    pub code: String,
+    pub service_date: NaiveDate,
+    pub payer_id: String,
+    pub plan: Option<String>,
+    pub fee_schedule_id: Option<Uuid>,
+    pub basis: PricingBasis,
+    pub billed: i64,
+    pub expected: Option<i64>,
+    pub paid: i64,
+    pub patient_responsibility: i64,
+    /// Paid plus the patient's share
+    pub actual: i64,
+    /// Actual less expected; negative for an underpayment
+    pub variance: Option<i64>,
+    /// Contractual adjustment the contract gives
+    pub contractual_adjustment: Option<i64>,
+    /// Contractual adjustment the payer took, its group CO adjustments
+    pub payer_contractual_adjustment: i64,
+    pub status: VarianceStatus,
+}
+
+/// Compare a payer's remittance lines for a charge with the charge's
+/// expected payment under the payer's contract
+pub fn reconcile(charge: &Charge, payments: &[&ChargePayment], expected: &ExpectedPayment) -> ChargeVariance {
+    let paid: i64 = payments.iter().map(|payment| payment.paid).sum();
+    let patient_responsibility: i64 = payments.iter().map(|payment| payment.patient_responsibility).sum();
+    let payer_contractual_adjustment = payments
+        .iter()
+        .map(|payment| adjusted(&payment.adjustments, AdjustmentGroup::ContractualObligation))
+        .sum();
+    let actual = paid + patient_responsibility;
+    let first = payments.first();
+    ChargeVariance {
+        charge_id: charge.id,
+        code: charge.code.clone(),
+        service_date: charge.service_date,
+        payer_id: first.map(|payment| payment.payer_id.clone()).unwrap_or_default(),
+        plan: first.and_then(|payment| payment.plan.clone()),
+        fee_schedule_id: expected.fee_schedule_id,
+        basis: expected.basis,
+        billed: expected.billed,
+        expected: expected.expected,
+        paid,
+        patient_responsibility,
+        actual,
+        variance: expected.expected.map(|expected| actual - expected),
+        contractual_adjustment: expected.contractual_adjustment,
+        payer_contractual_adjustment,
+        status: VarianceStatus::of(expected.expected, actual),
+    }
+}
+
+/// One payer's charges against their contracts over a report's period
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VarianceSummary {
+    pub payer_id: String,
+    pub charges: usize,
+    /// Expected allowed amounts of the priced charges
+    pub expected: i64,
+    /// Actual allowed amounts of the priced charges
+    pub actual: i64,
+    /// Actual less expected
+    pub variance: i64,
+    pub underpaid: usize,
+    pub overpaid: usize,
+    pub unpriced: usize,
+}
+
+/// Per-payer summaries of charge variances, by payer
+pub fn summarize(variances: &[ChargeVariance]) -> Vec<VarianceSummary> {
+    let mut summaries: Vec<VarianceSummary> = Vec::new();
+    for variance in variances {
+        let index = match summaries.iter().position(|summary| summary.payer_id == variance.payer_id) {
+            Some(index) => index,
+            None => {
+                summaries.push(VarianceSummary {
+                    payer_id: variance.payer_id.clone(),
+                    ..VarianceSummary::default()
+                });
+                summaries.len() - 1
+            }
+        };
+        let summary = &mut summaries[index];
+        summary.charges += 1;
+        if let Some(expected) = variance.expected {
+            summary.expected += expected;
+            summary.actual += variance.actual;
+            summary.variance += variance.actual - expected;
+        }
+        match variance.status {
+            VarianceStatus::Underpaid => summary.underpaid += 1,
+            VarianceStatus::Overpaid => summary.overpaid += 1,
+            VarianceStatus::Unpriced => summary.unpriced += 1,
+            VarianceStatus::AsExpected => {}
+        }
+    }
+    summaries.sort_by(|a, b| a.payer_id.cmp(&b.payer_id));
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::billing::billing_service::ChargeStatus;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn schedule(plan: Option<&str>, effective_date: NaiveDate, percent_of_charges: Option<i32>) -> FeeSchedule {
+        FeeSchedule {
+            id: Uuid::new_v4(),
+            payer_id: "60054".to_string(),
+            payer_name: "Aetna".to_string(),
+            plan: plan.map(str::to_string),
+            name: "Commercial".to_string(),
+            effective_date,
+            termination_date: None,
+            percent_of_charges,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn rate(code: &str, modifier: Option<&str>, rate: i64, effective_date: NaiveDate) -> ScheduleRate {
+        ScheduleRate {
+            code_system: BillingCodeSystem::Cpt,
+            code: code.to_string(),
+            modifier: modifier.map(str::to_string),
+            rate,
+            effective_date,
+            termination_date: None,
+        }
+    }
+
+    fn charge(code: &str, modifiers: &[&str], units: i32, unit_price: i64) -> Charge {
+        Charge {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            encounter_id: Uuid::new_v4(),
+            code_system: BillingCodeSystem::Cpt,
+            code: code.to_string(),
+            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+            ndc: None,
+            units,
+            unit_price,
+            service_date: date(2024, 6, 1),
+            status: ChargeStatus::Active,
+            captured_by: None,
+            void_reason: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_select_schedule_and_rate() {
+        let schedules = vec![
+            schedule(None, date(2023, 1, 1), None),
+            schedule(None, date(2024, 1, 1), None),
+            schedule(Some("PPO"), date(2022, 1, 1), None),
+        ];
+        let on = date(2024, 6, 1);
+        assert_eq!(select_schedule(&schedules, "60054", Some("PPO"), on).unwrap().id, schedules[2].id);
+        assert_eq!(select_schedule(&schedules, "60054", Some("HMO"), on).unwrap().id, schedules[1].id);
+        assert_eq!(select_schedule(&schedules, "60054", None, date(2023, 6, 1)).unwrap().id, schedules[0].id);
+        assert!(select_schedule(&schedules, "87726", None, on).is_none());
+
+        let rates = vec![
+            rate("71046", None, 6_000, date(2023, 1, 1)),
+            rate("71046", None, 6_500, date(2024, 1, 1)),
+            rate("71046", Some("26"), 1_500, date(2023, 1, 1)),
+        ];
+        assert_eq!(find_rate(&rates, "71046", &[], on).unwrap().rate, 6_500);
+        assert_eq!(find_rate(&rates, "71046", &[], date(2023, 6, 1)).unwrap().rate, 6_000);
+        assert_eq!(find_rate(&rates, "71046", &["26".to_string()], on).unwrap().rate, 1_500);
+        assert!(find_rate(&rates, "71046", &[], date(2022, 6, 1)).is_none());
+    }
+
+    #[test]
+    fn test_expected_payment() {
+        let percent = schedule(None, date(2024, 1, 1), Some(6_000));
+        let rates = vec![rate("99213", None, 9_000, date(2024, 1, 1))];
+
+        let expected = expected_payment(Some(&percent), &rates, &charge("99213", &[], 2, 15_000));
+        assert_eq!(expected.basis, PricingBasis::FeeSchedule);
+        assert_eq!(expected.expected, Some(18_000));
+        assert_eq!(expected.contractual_adjustment, Some(12_000));
+
+        // Lesser of billed and the rate
+        let cheap = expected_payment(Some(&percent), &rates, &charge("99213", &[], 1, 8_000));
+        assert_eq!((cheap.expected, cheap.contractual_adjustment), (Some(8_000), Some(0)));
+
+        let unlisted = expected_payment(Some(&percent), &rates, &charge("36415", &[], 1, 2_501));
+        assert_eq!((unlisted.basis, unlisted.expected), (PricingBasis::PercentOfCharges, Some(1_501)));
+
+        let no_percent = schedule(None, date(2024, 1, 1), None);
+        let unpriced = expected_payment(Some(&no_percent), &rates, &charge("36415", &[], 1, 2_500));
+        assert_eq!((unpriced.basis, unpriced.expected), (PricingBasis::Unpriced, None));
+        assert_eq!(expected_payment(None, &rates, &charge("99213", &[], 1, 15_000)).basis, PricingBasis::Unpriced);
+
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let percent = schedule(None, date(2024, 1, 1), None);
+        let rates = vec![rate("99213", None, 9_000, date(2024, 1, 1))];
+        let office_visit = charge("99213", &[], 1, 15_000);
+        let expected = expected_payment(Some(&percent), &rates, &office_visit);
+
+        let payment = |paid: i64, adjustments: Vec<RemittanceAdjustment>| ChargePayment {
+            id: Uuid::new_v4(),
+            charge_id: office_visit.id,
+            payer_id: "60054".to_string(),
+            plan: None,
+            payer_claim_id: None,
+            paid_date: date(2024, 7, 1),
+            paid,
+            patient_responsibility: adjusted(&adjustments, AdjustmentGroup::PatientResponsibility),
+            adjustments,
+            posted_by: None,
+            created_at: Utc::now(),
+        };
+        let adjustment = |group: AdjustmentGroup, amount: i64| RemittanceAdjustment {
+            group,
+            reason_code: "45".to_string(),
+            amount,
+        };
+
+        // Paid 65.00 with a 20.00 copay of an allowed 90.00
+        let short = payment(
+            6_500,
+            vec![
+                adjustment(AdjustmentGroup::ContractualObligation, 6_500),
+                adjustment(AdjustmentGroup::PatientResponsibility, 2_000),
+            ],
+        );
+        let variance = reconcile(&office_visit, &[&short], &expected);
+        assert_eq!((variance.actual, variance.variance), (8_500, Some(-500)));
+        assert_eq!(variance.status, VarianceStatus::Underpaid);
+        assert_eq!(variance.payer_contractual_adjustment, 6_500);
+
+        // The payer reverses the line and pays it again in full
+        let reversal = payment(-6_500, vec![adjustment(AdjustmentGroup::PatientResponsibility, -2_000)]);
+        let corrected = payment(7_000, vec![adjustment(AdjustmentGroup::PatientResponsibility, 2_000)]);
+        let variance = reconcile(&office_visit, &[&short, &reversal, &corrected], &expected);
+        assert_eq!((variance.actual, variance.status), (9_000, VarianceStatus::AsExpected));
+
+        let summaries = summarize(&[variance, reconcile(&office_visit, &[&short], &expected)]);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!((summaries[0].charges, summaries[0].underpaid, summaries[0].variance), (2, 1, -500));
+    }
+}
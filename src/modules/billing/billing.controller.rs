@@ -0,0 +1,456 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::billing::billing_contract::{
+    ChargePayment, ExpectedPayment, FeeSchedule, FeeScheduleRequest, PaymentRequest, ScheduleRate,
+};
+use crate::modules::billing::billing_edits::{CciEdit, EditFinding};
+use crate::modules::billing::billing_pmjay::{PmjayPackage, PmjayPackageRequest, PreauthRequest};
+use crate::modules::billing::billing_service::{
+    BillingCode, BillingCodeRequest, BillingCodeSystem, Charge, ChargeQuery, ChargeRequest, GstInvoice,
+    GstInvoiceQuery, GstInvoiceRequest, GstRegistration, GstRegistrationRequest, PostedPayment, VarianceQuery,
+    VarianceReport,
+};
+use crate::modules::billing::BillingService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+#[derive(Debug, Serialize)]
+pub struct ImportResponse {
+    pub imported: usize,
+}
+
+/// Reason a charge is voided or an invoice cancelled
+#[derive(Debug, Deserialize)]
+pub struct VoidRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PackageQuery {
+    pub specialty: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeeScheduleQuery {
+    pub payer_id: Option<String>,
+}
+
+/// The payer and plan whose contract prices a charge
+#[derive(Debug, Deserialize)]
+pub struct PayerQuery {
+    pub payer_id: String,
+    pub plan: Option<String>,
+}
+
+/// A patient's date of service to check the charges of
+#[derive(Debug, Deserialize)]
+pub struct EditQuery {
+    pub patient_id: Uuid,
+    pub service_date: NaiveDate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Billing controller for code lookup and charge capture
+pub struct BillingController {
+    billing_service: Arc<BillingService>,
+}
+
+impl BillingController {
+    /// Create new controller with injected service
+    pub fn new(billing_service: Arc<BillingService>) -> Self {
+        Self { billing_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/codes/:system/:code", Self::lookup_code, "Look up a CPT, HCPCS, NDC or modifier code")
+            .post("/codes/:system", Self::import_codes, "Load codes of a billing code set release")
+            .post("/cci-edits", Self::import_cci_edits, "Load NCCI procedure-to-procedure edits")
+            .post("/charges", Self::capture_charge, "Capture a charge")
+            .get("/charges", Self::list_charges, "List charges")
+            .get("/charges/:id", Self::get_charge, "Get charge by ID")
+            .post("/charges/:id/void", Self::void_charge, "Void a charge")
+            .get("/edits", Self::check_edits, "Check a patient's charges on a day against NCCI edits")
+            .post("/gst-registrations", Self::create_registration, "Register a GSTIN")
+            .get("/gst-registrations", Self::list_registrations, "List GST registrations")
+            .post("/invoices", Self::issue_invoice, "Issue a GST invoice")
+            .get("/invoices", Self::list_invoices, "List GST invoices")
+            .get("/invoices/:id", Self::get_invoice, "Get GST invoice by ID")
+            .post("/invoices/:id/cancel", Self::cancel_invoice, "Cancel a GST invoice")
+            .post("/pmjay/packages", Self::import_packages, "Load packages of the PM-JAY package master")
+            .get("/pmjay/packages", Self::list_packages, "List PM-JAY packages")
+            .get("/pmjay/packages/:code", Self::get_package, "Look up a PM-JAY package")
+            .post("/pmjay/preauth", Self::preauth_payload, "Generate a PM-JAY pre-authorization request")
+            .post("/fee-schedules", Self::create_fee_schedule, "Add a fee schedule to a payer contract")
+            .get("/fee-schedules", Self::list_fee_schedules, "List fee schedules")
+            .get("/fee-schedules/:id", Self::get_fee_schedule, "Get fee schedule by ID")
+            .post("/fee-schedules/:id/rates", Self::import_rates, "Load rates into a fee schedule")
+            .get("/fee-schedules/:id/rates", Self::list_rates, "List a fee schedule's rates")
+            .get("/charges/:id/expected", Self::expected_for_charge, "Price a charge under a payer contract")
+            .get("/charges/:id/payments", Self::list_payments, "List remittance lines posted against a charge")
+            .post("/payments", Self::post_payment, "Post a remittance line against a charge")
+            .get("/reimbursement-variance", Self::variance_report, "Report expected against actual reimbursement")
+            .with_state(self.billing_service.clone())
+    }
+
+    pub async fn lookup_code(
+        State(service): State<Arc<BillingService>>,
+        Path((system, code)): Path<(BillingCodeSystem, String)>,
+    ) -> Result<Json<BillingCode>, ErrorReply> {
+        match service.lookup_code(system, &code).await {
+            Ok(Some(code)) => Ok(Json(code)),
+            Ok(None) => Err(Self::not_found(&format!("{} code", system.as_str().to_uppercase()), &code)),
+            Err(e) => Err(Self::error_response("Failed to look up billing code", e)),
+        }
+    }
+
+    pub async fn import_codes(
+        State(service): State<Arc<BillingService>>,
+        Path(system): Path<BillingCodeSystem>,
+        Json(codes): Json<Vec<BillingCodeRequest>>,
+    ) -> Result<Json<ImportResponse>, ErrorReply> {
+        tracing::info!("Loading {} {} codes", codes.len(), system.as_str());
+
+        match service.import_codes(system, codes).await {
+            Ok(imported) => Ok(Json(ImportResponse { imported })),
+            Err(e) => Err(Self::error_response("Failed to load billing codes", e)),
+        }
+    }
+
+    pub async fn import_cci_edits(
+        State(service): State<Arc<BillingService>>,
+        Json(edits): Json<Vec<CciEdit>>,
+    ) -> Result<Json<ImportResponse>, ErrorReply> {
+        tracing::info!("Loading {} NCCI edits", edits.len());
+
+        match service.import_cci_edits(edits).await {
+            Ok(imported) => Ok(Json(ImportResponse { imported })),
+            Err(e) => Err(Self::error_response("Failed to load NCCI edits", e)),
+        }
+    }
+
+    /// Capture a charge; answers 400 with the edits it fails
+    pub async fn capture_charge(
+        State(service): State<Arc<BillingService>>,
+        headers: HeaderMap,
+        Json(request): Json<ChargeRequest>,
+    ) -> Result<(StatusCode, Json<Charge>), ErrorReply> {
+        tracing::info!("Capturing {} for encounter {}", request.code, request.encounter_id);
+
+        match service.capture_charge(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(charge) => Ok((StatusCode::CREATED, Json(charge))),
+            Err(e) => Err(Self::error_response("Failed to capture charge", e)),
+        }
+    }
+
+    pub async fn list_charges(
+        State(service): State<Arc<BillingService>>,
+        Query(query): Query<ChargeQuery>,
+    ) -> Result<Json<Vec<Charge>>, ErrorReply> {
+        match service.list_charges(&query).await {
+            Ok(charges) => Ok(Json(charges)),
+            Err(e) => Err(Self::error_response("Failed to list charges", e)),
+        }
+    }
+
+    pub async fn get_charge(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Charge>, ErrorReply> {
+        match service.get_charge(id).await {
+            Ok(Some(charge)) => Ok(Json(charge)),
+            Ok(None) => Err(Self::not_found("Charge", &id.to_string())),
+            Err(e) => Err(Self::error_response("Failed to get charge", e)),
+        }
+    }
+
+    pub async fn void_charge(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<VoidRequest>,
+    ) -> Result<Json<Charge>, ErrorReply> {
+        tracing::info!("Voiding charge {}", id);
+
+        match service.void_charge(id, request.reason).await {
+            Ok(Some(charge)) => Ok(Json(charge)),
+            Ok(None) => Err(Self::not_found("Charge", &id.to_string())),
+            Err(e) => Err(Self::error_response("Failed to void charge", e)),
+        }
+    }
+
+    pub async fn check_edits(
+        State(service): State<Arc<BillingService>>,
+        Query(query): Query<EditQuery>,
+    ) -> Result<Json<Vec<EditFinding>>, ErrorReply> {
+        match service.check_edits(query.patient_id, query.service_date).await {
+            Ok(findings) => Ok(Json(findings)),
+            Err(e) => Err(Self::error_response("Failed to check NCCI edits", e)),
+        }
+    }
+
+    pub async fn create_registration(
+        State(service): State<Arc<BillingService>>,
+        Json(request): Json<GstRegistrationRequest>,
+    ) -> Result<(StatusCode, Json<GstRegistration>), ErrorReply> {
+        tracing::info!("Registering GSTIN {}", request.gstin);
+
+        match service.create_registration(request).await {
+            Ok(registration) => Ok((StatusCode::CREATED, Json(registration))),
+            Err(e) => Err(Self::error_response("Failed to register GSTIN", e)),
+        }
+    }
+
+    pub async fn list_registrations(
+        State(service): State<Arc<BillingService>>,
+    ) -> Result<Json<Vec<GstRegistration>>, ErrorReply> {
+        match service.list_registrations().await {
+            Ok(registrations) => Ok(Json(registrations)),
+            Err(e) => Err(Self::error_response("Failed to list GST registrations", e)),
+        }
+    }
+
+    pub async fn issue_invoice(
+        State(service): State<Arc<BillingService>>,
+        headers: HeaderMap,
+        Json(request): Json<GstInvoiceRequest>,
+    ) -> Result<(StatusCode, Json<GstInvoice>), ErrorReply> {
+        tracing::info!("Issuing invoice for patient {}", request.patient_id);
+
+        match service.issue_invoice(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(invoice) => Ok((StatusCode::CREATED, Json(invoice))),
+            Err(e) => Err(Self::error_response("Failed to issue invoice", e)),
+        }
+    }
+
+    pub async fn list_invoices(
+        State(service): State<Arc<BillingService>>,
+        Query(query): Query<GstInvoiceQuery>,
+    ) -> Result<Json<Vec<GstInvoice>>, ErrorReply> {
+        match service.list_invoices(&query).await {
+            Ok(invoices) => Ok(Json(invoices)),
+            Err(e) => Err(Self::error_response("Failed to list invoices", e)),
+        }
+    }
+
+    pub async fn get_invoice(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<GstInvoice>, ErrorReply> {
+        match service.get_invoice(id).await {
+            Ok(Some(invoice)) => Ok(Json(invoice)),
+            Ok(None) => Err(Self::not_found("Invoice", &id.to_string())),
+            Err(e) => Err(Self::error_response("Failed to get invoice", e)),
+        }
+    }
+
+    pub async fn cancel_invoice(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<VoidRequest>,
+    ) -> Result<Json<GstInvoice>, ErrorReply> {
+        tracing::info!("Cancelling invoice {}", id);
+
+        match service.cancel_invoice(id, request.reason).await {
+            Ok(Some(invoice)) => Ok(Json(invoice)),
+            Ok(None) => Err(Self::not_found("Invoice", &id.to_string())),
+            Err(e) => Err(Self::error_response("Failed to cancel invoice", e)),
+        }
+    }
+
+    pub async fn import_packages(
+        State(service): State<Arc<BillingService>>,
+        Json(packages): Json<Vec<PmjayPackageRequest>>,
+    ) -> Result<Json<ImportResponse>, ErrorReply> {
+        tracing::info!("Loading {} PM-JAY packages", packages.len());
+
+        match service.import_packages(packages).await {
+            Ok(imported) => Ok(Json(ImportResponse { imported })),
+            Err(e) => Err(Self::error_response("Failed to load PM-JAY packages", e)),
+        }
+    }
+
+    pub async fn list_packages(
+        State(service): State<Arc<BillingService>>,
+        Query(query): Query<PackageQuery>,
+    ) -> Result<Json<Vec<PmjayPackage>>, ErrorReply> {
+        match service.list_packages(query.specialty.as_deref()).await {
+            Ok(packages) => Ok(Json(packages)),
+            Err(e) => Err(Self::error_response("Failed to list PM-JAY packages", e)),
+        }
+    }
+
+    pub async fn get_package(
+        State(service): State<Arc<BillingService>>,
+        Path(code): Path<String>,
+    ) -> Result<Json<PmjayPackage>, ErrorReply> {
+        match service.get_package(&code).await {
+            Ok(Some(package)) => Ok(Json(package)),
+            Ok(None) => Err(Self::not_found("PM-JAY package", &code)),
+            Err(e) => Err(Self::error_response("Failed to look up PM-JAY package", e)),
+        }
+    }
+
+    /// The pre-authorization request as a FHIR Bundle, to send to the
+    /// National Health Claims Exchange
+    pub async fn preauth_payload(
+        State(service): State<Arc<BillingService>>,
+        Json(request): Json<PreauthRequest>,
+    ) -> Result<Json<Value>, ErrorReply> {
+        tracing::info!("Generating PM-JAY pre-authorization for encounter {}", request.encounter_id);
+
+        match service.preauth_payload(request).await {
+            Ok(bundle) => Ok(Json(bundle)),
+            Err(e) => Err(Self::error_response("Failed to generate pre-authorization request", e)),
+        }
+    }
+
+    pub async fn create_fee_schedule(
+        State(service): State<Arc<BillingService>>,
+        Json(request): Json<FeeScheduleRequest>,
+    ) -> Result<(StatusCode, Json<FeeSchedule>), ErrorReply> {
+        tracing::info!("Adding fee schedule {} for payer {}", request.name, request.payer_id);
+
+        match service.create_fee_schedule(request).await {
+            Ok(schedule) => Ok((StatusCode::CREATED, Json(schedule))),
+            Err(e) => Err(Self::error_response("Failed to add fee schedule", e)),
+        }
+    }
+
+    pub async fn list_fee_schedules(
+        State(service): State<Arc<BillingService>>,
+        Query(query): Query<FeeScheduleQuery>,
+    ) -> Result<Json<Vec<FeeSchedule>>, ErrorReply> {
+        match service.list_fee_schedules(query.payer_id.as_deref()).await {
+            Ok(schedules) => Ok(Json(schedules)),
+            Err(e) => Err(Self::error_response("Failed to list fee schedules", e)),
+        }
+    }
+
+    pub async fn get_fee_schedule(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<FeeSchedule>, ErrorReply> {
+        match service.get_fee_schedule(id).await {
+            Ok(Some(schedule)) => Ok(Json(schedule)),
+            Ok(None) => Err(Self::not_found("Fee schedule", &id.to_string())),
+            Err(e) => Err(Self::error_response("Failed to get fee schedule", e)),
+        }
+    }
+
+    pub async fn import_rates(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+        Json(rates): Json<Vec<ScheduleRate>>,
+    ) -> Result<Json<ImportResponse>, ErrorReply> {
+        tracing::info!("Loading {} rates into fee schedule {}", rates.len(), id);
+
+        match service.import_rates(id, rates).await {
+            Ok(Some(imported)) => Ok(Json(ImportResponse { imported })),
+            Ok(None) => Err(Self::not_found("Fee schedule", &id.to_string())),
+            Err(e) => Err(Self::error_response("Failed to load fee schedule rates", e)),
+        }
+    }
+
+    pub async fn list_rates(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<ScheduleRate>>, ErrorReply> {
+        match service.list_rates(id).await {
+            Ok(rates) => Ok(Json(rates)),
+            Err(e) => Err(Self::error_response("Failed to list fee schedule rates", e)),
+        }
+    }
+
+    pub async fn expected_for_charge(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+        Query(query): Query<PayerQuery>,
+    ) -> Result<Json<ExpectedPayment>, ErrorReply> {
+        match service.expected_for_charge(id, &query.payer_id, query.plan.as_deref()).await {
+            Ok(Some(expected)) => Ok(Json(expected)),
+            Ok(None) => Err(Self::not_found("Charge", &id.to_string())),
+            Err(e) => Err(Self::error_response("Failed to price charge", e)),
+        }
+    }
+
+    pub async fn list_payments(
+        State(service): State<Arc<BillingService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<ChargePayment>>, ErrorReply> {
+        match service.list_payments(id).await {
+            Ok(payments) => Ok(Json(payments)),
+            Err(e) => Err(Self::error_response("Failed to list payments", e)),
+        }
+    }
+
+    /// Post a remittance line; answers with the charge's variance from its
+    /// contract
+    pub async fn post_payment(
+        State(service): State<Arc<BillingService>>,
+        headers: HeaderMap,
+        Json(request): Json<PaymentRequest>,
+    ) -> Result<(StatusCode, Json<PostedPayment>), ErrorReply> {
+        tracing::info!("Posting payment by {} against charge {}", request.payer_id, request.charge_id);
+
+        match service.post_payment(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(posted) => Ok((StatusCode::CREATED, Json(posted))),
+            Err(e) => Err(Self::error_response("Failed to post payment", e)),
+        }
+    }
+
+    pub async fn variance_report(
+        State(service): State<Arc<BillingService>>,
+        Query(query): Query<VarianceQuery>,
+    ) -> Result<Json<VarianceReport>, ErrorReply> {
+        match service.variance_report(&query).await {
+            Ok(report) => Ok(Json(report)),
+            Err(e) => Err(Self::error_response("Failed to report reimbursement variance", e)),
+        }
+    }
+
+    fn not_found(what: &str, id: &str) -> ErrorReply {
+        tracing::warn!("{} not found: {}", what, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", what),
+                message: format!("{} {} not found", what, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::standards::terminology::TerminologyService;
+
+/// Modifiers NCCI accepts as showing that a column two service was distinct
+/// from the column one service: separate encounter, structure, practitioner
+/// or session, or another anatomical site
+pub const NCCI_MODIFIERS: &[&str] = &[
+    "24", "25", "27", "57", "58", "59", "78", "79", "91", "XE", "XP", "XS", "XU", "E1", "E2", "E3", "E4", "F1", "F2",
+    "F3", "F4", "F5", "F6", "F7", "F8", "F9", "FA", "LC", "LD", "LM", "LT", "RC", "RI", "RT", "T1", "T2", "T3", "T4",
+    "T5", "T6", "T7", "T8", "T9", "TA",
+];
+
+/// Most modifiers a claim line carries
+pub const MAX_MODIFIERS: usize = 4;
+
+/// Whether an NCCI edit can be bypassed with a modifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModifierIndicator {
+    /// The codes are never billed together
+    NotAllowed,
+    /// An NCCI modifier on either line allows both
+    Allowed,
+    /// The edit does not apply
+    NotApplicable,
+}
+
+impl ModifierIndicator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModifierIndicator::NotAllowed => "0",
+            ModifierIndicator::Allowed => "1",
+            ModifierIndicator::NotApplicable => "9",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "0" => Some(ModifierIndicator::NotAllowed),
+            "1" => Some(ModifierIndicator::Allowed),
+            "9" => Some(ModifierIndicator::NotApplicable),
+            _ => None,
+        }
+    }
+}
+
+/// An NCCI procedure-to-procedure edit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CciEdit {
+    pub column_one: String,
+    pub column_two: String,
+    pub effective_date: NaiveDate,
+    pub deletion_date: Option<NaiveDate>,
+    pub modifier_indicator: ModifierIndicator,
+}
+
+impl CciEdit {
+    /// Whether the edit applies to services on a date
+    pub fn in_effect(&self, on: NaiveDate) -> bool {
+        self.effective_date <= on
+            && self.deletion_date.is_none_or(|deleted| on < deleted)
+            && self.modifier_indicator != ModifierIndicator::NotApplicable
+    }
+}
+
+/// A charge line as the edits see it
+#[derive(Debug, Clone)]
+pub struct EditLine {
+    /// `None` for a line not captured yet
+    pub charge_id: Option<Uuid>,
+    pub code: String,
+    pub modifiers: Vec<String>,
+}
+
+impl EditLine {
+    fn has_ncci_modifier(&self) -> bool {
+        self.modifiers.iter().any(|modifier| NCCI_MODIFIERS.contains(&modifier.as_str()))
+    }
+}
+
+/// A pair of lines an NCCI edit does not allow together
+#[derive(Debug, Clone, Serialize)]
+pub struct EditFinding {
+    pub column_one: String,
+    pub column_two: String,
+    pub column_one_charge: Option<Uuid>,
+    pub column_two_charge: Option<Uuid>,
+    pub modifier_indicator: ModifierIndicator,
+    pub message: String,
+}
+
+/// Pairs of a patient's lines on one date of service that NCCI edits in
+/// effect on that date do not allow together
+pub fn pair_findings(lines: &[EditLine], edits: &[CciEdit], on: NaiveDate) -> Vec<EditFinding> {
+    let mut findings = Vec::new();
+    for edit in edits.iter().filter(|edit| edit.in_effect(on)) {
+        for one in lines.iter().filter(|line| line.code == edit.column_one) {
+            for two in lines.iter().filter(|line| line.code == edit.column_two) {
+                let bypassed = edit.modifier_indicator == ModifierIndicator::Allowed
+                    && (one.has_ncci_modifier() || two.has_ncci_modifier());
+                if bypassed {
+                    continue;
+                }
+                let message = match edit.modifier_indicator {
+                    ModifierIndicator::Allowed => format!(
+                        "{} is bundled into {} on the same day unless a modifier such as 59 or XU shows the \
+                         services were distinct",
+                        edit.column_two, edit.column_one
+                    ),
+                    _ => format!("{} is bundled into {} and is never billed with it", edit.column_two, edit.column_one),
+                };
+                findings.push(EditFinding {
+                    column_one: edit.column_one.clone(),
+                    column_two: edit.column_two.clone(),
+                    column_one_charge: one.charge_id,
+                    column_two_charge: two.charge_id,
+                    modifier_indicator: edit.modifier_indicator,
+                    message,
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// Upper-case a line's modifiers and check them: well formed, at most four,
+/// none twice, and no combination that contradicts itself
+pub fn check_modifiers(modifiers: &[String]) -> Result<Vec<String>, String> {
+    let modifiers: Vec<String> = modifiers.iter().map(|modifier| modifier.trim().to_ascii_uppercase()).collect();
+    if let Some(modifier) = modifiers.iter().find(|modifier| !TerminologyService::is_modifier(modifier)) {
+        return Err(format!("{:?} is not a modifier", modifier));
+    }
+    if modifiers.len() > MAX_MODIFIERS {
+        return Err(format!("A charge carries at most {} modifiers", MAX_MODIFIERS));
+    }
+    for (index, modifier) in modifiers.iter().enumerate() {
+        if modifiers[..index].contains(modifier) {
+            return Err(format!("Modifier {} is given twice", modifier));
+        }
+    }
+
+    let has = |modifier: &str| modifiers.iter().any(|m| m == modifier);
+    let conflicts = [
+        ("26", "TC", "the professional and technical components are billed on separate lines"),
+        ("LT", "RT", "a service on both sides is billed with modifier 50"),
+        ("50", "LT", "modifier 50 already means both sides"),
+        ("50", "RT", "modifier 50 already means both sides"),
+    ];
+    if let Some((one, two, why)) = conflicts.iter().find(|(one, two, _)| has(one) && has(two)) {
+        return Err(format!("Modifiers {} and {} do not go together: {}", one, two, why));
+    }
+    Ok(modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(one: &str, two: &str, indicator: ModifierIndicator) -> CciEdit {
+        CciEdit {
+            column_one: one.to_string(),
+            column_two: two.to_string(),
+            effective_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            deletion_date: Some(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()),
+            modifier_indicator: indicator,
+        }
+    }
+
+    fn line(code: &str, modifiers: &[&str]) -> EditLine {
+        EditLine {
+            charge_id: Some(Uuid::new_v4()),
+            code: code.to_string(),
+            modifiers: modifiers.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_pair_findings() {
+        let on = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let edits = vec![
+            edit("99213", "36415", ModifierIndicator::Allowed),
+            edit("93000", "93010", ModifierIndicator::NotAllowed),
+            edit("99214", "36415", ModifierIndicator::NotApplicable),
+        ];
+
+        let findings = pair_findings(&[line("99213", &[]), line("36415", &[])], &edits, on);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].column_two, "36415");
+        assert!(pair_findings(&[line("99213", &["25"]), line("36415", &[])], &edits, on).is_empty());
+        assert!(pair_findings(&[line("99213", &[]), line("36415", &["59"])], &edits, on).is_empty());
+
+        // Indicator 0 is not bypassed, indicator 9 never applies
+        assert_eq!(pair_findings(&[line("93000", &[]), line("93010", &["59"])], &edits, on).len(), 1);
+        assert!(pair_findings(&[line("99214", &[]), line("36415", &[])], &edits, on).is_empty());
+
+        // Deleted edits no longer apply
+        let later = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        assert!(pair_findings(&[line("99213", &[]), line("36415", &[])], &edits, later).is_empty());
+    }
+
+    #[test]
+    fn test_check_modifiers() {
+        let modifiers = |values: &[&str]| values.iter().map(|m| m.to_string()).collect::<Vec<String>>();
+        assert_eq!(check_modifiers(&modifiers(&["lt", " 59"])).unwrap(), vec!["LT", "59"]);
+        assert!(check_modifiers(&modifiers(&["5"])).is_err());
+        assert!(check_modifiers(&modifiers(&["25", "25"])).is_err());
+        assert!(check_modifiers(&modifiers(&["25", "59", "LT", "76", "77"])).is_err());
+        assert!(check_modifiers(&modifiers(&["26", "TC"])).is_err());
+        assert!(check_modifiers(&modifiers(&["50", "RT"])).is_err());
+    }
+}
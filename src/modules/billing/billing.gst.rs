@@ -0,0 +1,308 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Rates GST is levied at, in percent. Health care services by clinical
+/// establishments are exempt; medicines and other goods supplied on their
+/// own are taxed at one of the others.
+pub const GST_RATES: &[f64] = &[0.0, 0.1, 0.25, 1.5, 3.0, 5.0, 12.0, 18.0, 28.0, 40.0];
+
+/// Longest an invoice number may be
+pub const MAX_INVOICE_NUMBER_LEN: usize = 16;
+
+const GSTIN_CHARSET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Whether a value is a GSTIN: a two-digit state code, the holder's PAN, an
+/// entity number, `Z` and a check character
+pub fn is_gstin(gstin: &str) -> bool {
+    let chars: Vec<char> = gstin.chars().collect();
+    if chars.len() != 15 {
+        return false;
+    }
+    let shaped = chars[..2].iter().all(|c| c.is_ascii_digit())
+        && chars[2..7].iter().all(|c| c.is_ascii_uppercase())
+        && chars[7..11].iter().all(|c| c.is_ascii_digit())
+        && chars[11].is_ascii_uppercase()
+        && (chars[12].is_ascii_digit() || chars[12].is_ascii_uppercase())
+        && chars[12] != '0'
+        && chars[13] == 'Z';
+    shaped && gstin_check_char(&chars[..14]) == Some(chars[14])
+}
+
+/// Check character of a GSTIN's first 14 characters: base 36 digits
+/// weighted alternately 1 and 2, the products' base 36 digits summed
+fn gstin_check_char(chars: &[char]) -> Option<char> {
+    let mut sum = 0;
+    for (index, c) in chars.iter().enumerate() {
+        let value = GSTIN_CHARSET.find(*c)?;
+        let product = value * if index % 2 == 0 { 1 } else { 2 };
+        sum += product / 36 + product % 36;
+    }
+    GSTIN_CHARSET.chars().nth((36 - sum % 36) % 36)
+}
+
+/// State code a GSTIN is registered in, e.g. `27` for Maharashtra
+pub fn gstin_state(gstin: &str) -> &str {
+    &gstin[..2]
+}
+
+/// Whether a value is a GST state code, e.g. `27`, or `97` for other
+/// territory
+pub fn is_state_code(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_digit()) && code != "00"
+}
+
+/// Whether a value is an HSN code of goods or a SAC of services: 4, 6 or 8
+/// digits
+pub fn is_hsn_sac(code: &str) -> bool {
+    matches!(code.len(), 4 | 6 | 8) && code.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Financial year, April to March, a date falls in, e.g. `2024-25`
+pub fn financial_year(date: NaiveDate) -> String {
+    let start = if date.month() >= 4 { date.year() } else { date.year() - 1 };
+    format!("{}-{:02}", start, (start + 1) % 100)
+}
+
+/// Number of an invoice in a series, e.g. `INV/24-25/00042`, or `None`
+/// once the series has outgrown the 16 characters an invoice number has
+pub fn invoice_number(prefix: &str, financial_year: &str, number: i32) -> Option<String> {
+    let number = format!("{}/{}/{:05}", prefix, &financial_year[2..], number);
+    (number.len() <= MAX_INVOICE_NUMBER_LEN).then_some(number)
+}
+
+/// Rate in hundredths of a percent, which tax is computed in
+fn rate_basis_points(rate: f64) -> i64 {
+    (rate * 100.0).round() as i64
+}
+
+/// `basis_points` of `amount` in hundredths of a percent, halved for each
+/// of CGST and SGST, rounded half up to the paisa
+fn tax(amount: i64, basis_points: i64, halves: i64) -> i64 {
+    let divisor = 10_000 * halves;
+    (amount * basis_points + divisor / 2) / divisor
+}
+
+/// Whether a supply is within the supplier's state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplyType {
+    /// Taxed CGST and SGST
+    IntraState,
+    /// Taxed IGST
+    InterState,
+}
+
+impl SupplyType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SupplyType::IntraState => "intra_state",
+            SupplyType::InterState => "inter_state",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "intra_state" => Some(SupplyType::IntraState),
+            "inter_state" => Some(SupplyType::InterState),
+            _ => None,
+        }
+    }
+
+    /// Supply from a supplier's state to a place of supply
+    pub fn between(supplier_state: &str, place_of_supply: &str) -> Self {
+        if supplier_state == place_of_supply {
+            SupplyType::IntraState
+        } else {
+            SupplyType::InterState
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentType {
+    /// For supplies some tax is charged on
+    TaxInvoice,
+    /// For exempt supplies only, which no tax is charged on
+    BillOfSupply,
+}
+
+impl DocumentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentType::TaxInvoice => "tax_invoice",
+            DocumentType::BillOfSupply => "bill_of_supply",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "tax_invoice" => Some(DocumentType::TaxInvoice),
+            "bill_of_supply" => Some(DocumentType::BillOfSupply),
+            _ => None,
+        }
+    }
+}
+
+/// A line to invoice. Amounts are in paise.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GstLineRequest {
+    pub description: String,
+    /// HSN code of goods or SAC of services, e.g. 9993 for health services
+    pub hsn_sac: String,
+    pub quantity: i32,
+    pub unit_price: i64,
+    #[serde(default)]
+    pub discount: i64,
+    /// GST rate in percent, 0 for exempt supplies
+    pub gst_rate: f64,
+}
+
+impl GstLineRequest {
+    /// Check the line's shape
+    pub fn check(&self) -> Result<(), String> {
+        if self.description.trim().is_empty() {
+            return Err("An invoice line needs a description".to_string());
+        }
+        if !is_hsn_sac(self.hsn_sac.trim()) {
+            return Err(format!("{:?} is not an HSN code or SAC", self.hsn_sac));
+        }
+        if self.quantity <= 0 {
+            return Err("An invoice line is for at least one unit".to_string());
+        }
+        if self.unit_price < 0 || self.discount < 0 {
+            return Err("An invoice line's price and discount cannot be negative".to_string());
+        }
+        if self.discount > self.unit_price * i64::from(self.quantity) {
+            return Err(format!("The discount on {} is more than its price", self.description.trim()));
+        }
+        if !GST_RATES.iter().any(|rate| rate_basis_points(*rate) == rate_basis_points(self.gst_rate)) {
+            return Err(format!("{}% is not a GST rate", self.gst_rate));
+        }
+        Ok(())
+    }
+}
+
+/// An invoiced line with its tax. Amounts are in paise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GstLine {
+    pub description: String,
+    pub hsn_sac: String,
+    pub quantity: i32,
+    pub unit_price: i64,
+    pub discount: i64,
+    pub gst_rate: f64,
+    /// Price less discount, which tax is charged on
+    pub taxable_value: i64,
+    pub cgst: i64,
+    pub sgst: i64,
+    pub igst: i64,
+    pub total: i64,
+}
+
+/// An invoice's amounts, in paise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct GstTotals {
+    pub taxable_value: i64,
+    pub cgst: i64,
+    pub sgst: i64,
+    pub igst: i64,
+    pub total: i64,
+}
+
+/// Tax on checked lines, each rounded to the paisa on its own, and the
+/// invoice's totals
+pub fn tax_breakup(lines: &[GstLineRequest], supply: SupplyType) -> (Vec<GstLine>, GstTotals) {
+    let mut totals = GstTotals::default();
+    let lines = lines
+        .iter()
+        .map(|line| {
+            let taxable_value = line.unit_price * i64::from(line.quantity) - line.discount;
+            let basis_points = rate_basis_points(line.gst_rate);
+            let (cgst, sgst, igst) = match supply {
+                SupplyType::IntraState => {
+                    let half = tax(taxable_value, basis_points, 2);
+                    (half, half, 0)
+                }
+                SupplyType::InterState => (0, 0, tax(taxable_value, basis_points, 1)),
+            };
+            let total = taxable_value + cgst + sgst + igst;
+            totals.taxable_value += taxable_value;
+            totals.cgst += cgst;
+            totals.sgst += sgst;
+            totals.igst += igst;
+            totals.total += total;
+            GstLine {
+                description: line.description.trim().to_string(),
+                hsn_sac: line.hsn_sac.trim().to_string(),
+                quantity: line.quantity,
+                unit_price: line.unit_price,
+                discount: line.discount,
+                gst_rate: line.gst_rate,
+                taxable_value,
+                cgst,
+                sgst,
+                igst,
+                total,
+            }
+        })
+        .collect();
+    (lines, totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(unit_price: i64, quantity: i32, gst_rate: f64) -> GstLineRequest {
+        GstLineRequest {
+            description: "Item".to_string(),
+            hsn_sac: "3004".to_string(),
+            quantity,
+            unit_price,
+            discount: 0,
+            gst_rate,
+        }
+    }
+
+    #[test]
+    fn test_gstin_and_numbering() {
+        assert!(is_gstin("27AAPFU0939F1ZV"));
+        assert!(!is_gstin("27AAPFU0939F1ZW"));
+        assert!(!is_gstin("27AAPFU0939F1Z"));
+        assert_eq!(gstin_state("27AAPFU0939F1ZV"), "27");
+
+        assert_eq!(financial_year(NaiveDate::from_ymd_opt(2024, 4, 1).unwrap()), "2024-25");
+        assert_eq!(financial_year(NaiveDate::from_ymd_opt(2025, 3, 31).unwrap()), "2024-25");
+        assert_eq!(financial_year(NaiveDate::from_ymd_opt(2099, 12, 1).unwrap()), "2099-00");
+        assert_eq!(invoice_number("INV", "2024-25", 42).as_deref(), Some("INV/24-25/00042"));
+        assert_eq!(invoice_number("HOSP", "2024-25", 99_999).as_deref(), Some("HOSP/24-25/99999"));
+        assert!(invoice_number("HOSP", "2024-25", 100_000).is_none());
+    }
+
+    #[test]
+    fn test_tax_breakup() {
+        let mut discounted = line(10_000, 3, 18.0);
+        discounted.discount = 1_001;
+        let lines = vec![discounted, line(33_333, 1, 5.0), line(50_000, 1, 0.0)];
+        assert!(lines.iter().all(|line| line.check().is_ok()));
+
+        let (intra, totals) = tax_breakup(&lines, SupplyType::IntraState);
+        // 18% of 289.99 is 52.1982, 26.0991 each half
+        assert_eq!((intra[0].cgst, intra[0].sgst, intra[0].igst), (2_610, 2_610, 0));
+        assert_eq!((intra[1].cgst, intra[1].sgst), (833, 833));
+        assert_eq!(intra[2].total, 50_000);
+        assert_eq!(totals.taxable_value, 28_999 + 33_333 + 50_000);
+        assert_eq!(totals.total, totals.taxable_value + 2 * (2_610 + 833));
+
+        let (inter, totals) = tax_breakup(&lines, SupplyType::InterState);
+        assert_eq!((inter[0].cgst, inter[0].igst), (0, 5_220));
+        assert_eq!(inter[1].igst, 1_667);
+        assert_eq!(totals.igst, 5_220 + 1_667);
+
+        assert!(line(100, 1, 7.0).check().is_err());
+        assert!(line(100, 0, 5.0).check().is_err());
+        assert!(GstLineRequest { hsn_sac: "99".to_string(), ..line(100, 1, 5.0) }.check().is_err());
+        assert_eq!(SupplyType::between("27", "29"), SupplyType::InterState);
+    }
+}
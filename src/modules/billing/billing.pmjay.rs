@@ -0,0 +1,297 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::models::constants::{
+    BUNDLE_RESOURCE_TYPE, CLAIM_RESOURCE_TYPE, CLAIM_TYPE_SYSTEM, COVERAGE_RESOURCE_TYPE, HFR_FACILITY_SYSTEM,
+    ICD_10_SYSTEM, NHCX_PARTICIPANT_SYSTEM, ORGANIZATION_RESOURCE_TYPE, PMJAY_BENEFICIARY_SYSTEM,
+    PMJAY_HOSPITAL_SYSTEM, PMJAY_PACKAGE_SYSTEM, PROCESS_PRIORITY_SYSTEM,
+};
+use crate::models::Patient;
+use crate::modules::patient::PatientController;
+use crate::modules::vital_record::vital_record_cause::normalize_icd10;
+
+/// The hospital as it is empanelled under PM-JAY, and the payer its
+/// pre-authorization requests go to on the National Health Claims Exchange
+#[derive(Debug, Clone, Default)]
+pub struct PmjayConfig {
+    pub hospital_id: Option<String>,
+    pub hospital_name: Option<String>,
+    /// Health Facility Registry ID
+    pub hfr_id: Option<String>,
+    /// NHCX participant code of the State Health Agency
+    pub payer_id: Option<String>,
+    pub payer_name: Option<String>,
+}
+
+impl PmjayConfig {
+    /// Settings from `PMJAY_HOSPITAL_ID`, `PMJAY_HOSPITAL_NAME`,
+    /// `HFR_FACILITY_ID`, `PMJAY_PAYER_ID` and `PMJAY_PAYER_NAME`
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        Self {
+            hospital_id: var("PMJAY_HOSPITAL_ID"),
+            hospital_name: var("PMJAY_HOSPITAL_NAME"),
+            hfr_id: var("HFR_FACILITY_ID"),
+            payer_id: var("PMJAY_PAYER_ID"),
+            payer_name: var("PMJAY_PAYER_NAME"),
+        }
+    }
+}
+
+/// A package code in its stored form, if it is shaped like one: a letter,
+/// then letters, digits and dots, e.g. `SG039A` or `M1.1`
+pub fn normalize_package_code(code: &str) -> Option<String> {
+    let code = code.trim().to_ascii_uppercase();
+    let valid = (2..=10).contains(&code.len())
+        && code.starts_with(|c: char| c.is_ascii_uppercase())
+        && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '.');
+    valid.then_some(code)
+}
+
+/// A health benefit package of the PM-JAY package master
+#[derive(Debug, Clone, Serialize)]
+pub struct PmjayPackage {
+    pub code: String,
+    pub specialty: String,
+    pub name: String,
+    /// National rate in paise
+    pub base_rate: i64,
+    pub preauth_required: bool,
+    pub day_care: bool,
+    /// Health Benefit Package master release, e.g. `HBP 2022`
+    pub hbp_version: String,
+    pub effective_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
+}
+
+impl PmjayPackage {
+    /// Whether the package may be booked for an admission on a date
+    pub fn in_effect(&self, on: NaiveDate) -> bool {
+        self.effective_date <= on && self.termination_date.is_none_or(|terminated| on <= terminated)
+    }
+}
+
+/// A package as the package master gives it
+#[derive(Debug, Clone, Deserialize)]
+pub struct PmjayPackageRequest {
+    pub code: String,
+    pub specialty: String,
+    pub name: String,
+    pub base_rate: i64,
+    #[serde(default = "default_preauth_required")]
+    pub preauth_required: bool,
+    #[serde(default)]
+    pub day_care: bool,
+    pub hbp_version: String,
+    pub effective_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
+}
+
+fn default_preauth_required() -> bool {
+    true
+}
+
+/// An admission to request pre-authorization of packages for
+#[derive(Debug, Clone, Deserialize)]
+pub struct PreauthRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Uuid,
+    /// The patient's PM-JAY ID, from beneficiary identification
+    pub beneficiary_id: String,
+    pub package_codes: Vec<String>,
+    /// ICD-10 codes of the diagnoses the packages treat
+    pub diagnoses: Vec<String>,
+    pub admission_date: NaiveDate,
+}
+
+impl PreauthRequest {
+    /// Check the request's shape and put its codes in their stored form
+    pub fn check(&mut self) -> Result<(), String> {
+        self.beneficiary_id = self.beneficiary_id.trim().to_ascii_uppercase();
+        if self.beneficiary_id.is_empty() {
+            return Err("A pre-authorization request needs the patient's PM-JAY ID".to_string());
+        }
+        if self.package_codes.is_empty() {
+            return Err("A pre-authorization request is for at least one package".to_string());
+        }
+        if self.diagnoses.is_empty() {
+            return Err("A pre-authorization request needs a diagnosis".to_string());
+        }
+        self.package_codes = self
+            .package_codes
+            .iter()
+            .map(|code| normalize_package_code(code).ok_or_else(|| format!("{:?} is not a PM-JAY package code", code)))
+            .collect::<Result<_, _>>()?;
+        for (index, code) in self.package_codes.iter().enumerate() {
+            if self.package_codes[..index].contains(code) {
+                return Err(format!("Package {} is given twice", code));
+            }
+        }
+        self.diagnoses = self.diagnoses.iter().map(|code| normalize_icd10(code)).collect::<Result<_, _>>()?;
+        Ok(())
+    }
+}
+
+/// Rupees of an amount in paise, as FHIR Money
+fn money(paise: i64) -> Value {
+    json!({ "value": paise as f64 / 100.0, "currency": "INR" })
+}
+
+/// A pre-authorization request as the FHIR Bundle the National Health
+/// Claims Exchange takes: a Claim for pre-authorization of the packages,
+/// the patient, their PM-JAY coverage, the hospital and the payer
+pub fn preauth_bundle(
+    id: Uuid,
+    request: &PreauthRequest,
+    packages: &[PmjayPackage],
+    patient: Patient,
+    config: &PmjayConfig,
+    created: DateTime<Utc>,
+) -> Value {
+    let patient_url = format!("Patient/{}", patient.id);
+    let coverage_url = format!("Coverage/{}", id);
+    let provider_url = "Organization/provider";
+    let payer_url = "Organization/payer";
+
+    let mut provider_identifiers = Vec::new();
+    if let Some(hfr_id) = &config.hfr_id {
+        provider_identifiers.push(json!({ "system": HFR_FACILITY_SYSTEM, "value": hfr_id }));
+    }
+    if let Some(hospital_id) = &config.hospital_id {
+        provider_identifiers.push(json!({ "system": PMJAY_HOSPITAL_SYSTEM, "value": hospital_id }));
+    }
+    let payer_identifiers: Vec<Value> = config
+        .payer_id
+        .iter()
+        .map(|payer_id| json!({ "system": NHCX_PARTICIPANT_SYSTEM, "value": payer_id }))
+        .collect();
+
+    let diagnoses: Vec<Value> = request
+        .diagnoses
+        .iter()
+        .enumerate()
+        .map(|(index, code)| {
+            json!({
+                "sequence": index + 1,
+                "diagnosisCodeableConcept": { "coding": [{ "system": ICD_10_SYSTEM, "code": code }] },
+            })
+        })
+        .collect();
+    let items: Vec<Value> = packages
+        .iter()
+        .enumerate()
+        .map(|(index, package)| {
+            json!({
+                "sequence": index + 1,
+                "diagnosisSequence": (1..=diagnoses.len()).collect::<Vec<usize>>(),
+                "productOrService": {
+                    "coding": [{ "system": PMJAY_PACKAGE_SYSTEM, "code": package.code, "display": package.name }],
+                },
+                "servicedDate": request.admission_date,
+                "unitPrice": money(package.base_rate),
+                "net": money(package.base_rate),
+                "encounter": [{ "reference": format!("Encounter/{}", request.encounter_id) }],
+            })
+        })
+        .collect();
+    let total: i64 = packages.iter().map(|package| package.base_rate).sum();
+
+    let claim = json!({
+        "resourceType": CLAIM_RESOURCE_TYPE,
+        "id": id,
+        "status": "active",
+        "type": { "coding": [{ "system": CLAIM_TYPE_SYSTEM, "code": "institutional" }] },
+        "use": "preauthorization",
+        "patient": { "reference": patient_url },
+        "billablePeriod": { "start": request.admission_date },
+        "created": created,
+        "insurer": { "reference": payer_url },
+        "provider": { "reference": provider_url },
+        "priority": { "coding": [{ "system": PROCESS_PRIORITY_SYSTEM, "code": "normal" }] },
+        "insurance": [{ "sequence": 1, "focal": true, "coverage": { "reference": coverage_url } }],
+        "diagnosis": diagnoses,
+        "item": items,
+        "total": money(total),
+    });
+    let coverage = json!({
+        "resourceType": COVERAGE_RESOURCE_TYPE,
+        "id": id,
+        "status": "active",
+        "identifier": [{ "system": PMJAY_BENEFICIARY_SYSTEM, "value": request.beneficiary_id }],
+        "subscriberId": request.beneficiary_id,
+        "beneficiary": { "reference": patient_url },
+        "payor": [{ "reference": payer_url }],
+    });
+    let provider = json!({
+        "resourceType": ORGANIZATION_RESOURCE_TYPE,
+        "id": "provider",
+        "identifier": provider_identifiers,
+        "name": config.hospital_name,
+    });
+    let payer = json!({
+        "resourceType": ORGANIZATION_RESOURCE_TYPE,
+        "id": "payer",
+        "identifier": payer_identifiers,
+        "name": config.payer_name,
+    });
+
+    json!({
+        "resourceType": BUNDLE_RESOURCE_TYPE,
+        "id": id,
+        "type": "collection",
+        "timestamp": created,
+        "entry": [
+            { "fullUrl": format!("Claim/{}", id), "resource": claim },
+            { "fullUrl": patient_url, "resource": PatientController::patient_to_response(patient) },
+            { "fullUrl": coverage_url, "resource": coverage },
+            { "fullUrl": provider_url, "resource": provider },
+            { "fullUrl": payer_url, "resource": payer },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> PreauthRequest {
+        PreauthRequest {
+            patient_id: Uuid::new_v4(),
+            encounter_id: Uuid::new_v4(),
+            beneficiary_id: " pz1234567 ".to_string(),
+            package_codes: vec!["sg039a".to_string(), "MG001A".to_string()],
+            diagnoses: vec!["k35.8".to_string()],
+            admission_date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_preauth_request_check() {
+        let mut preauth = request();
+        preauth.check().unwrap();
+        assert_eq!(preauth.beneficiary_id, "PZ1234567");
+        assert_eq!(preauth.package_codes, vec!["SG039A", "MG001A"]);
+        assert_eq!(preauth.diagnoses, vec!["K35.8"]);
+
+        let mut twice = PreauthRequest {
+            package_codes: vec!["SG039A".to_string(), "sg039a".to_string()],
+            ..request()
+        };
+        assert!(twice.check().is_err());
+        let mut no_packages = PreauthRequest {
+            package_codes: Vec::new(),
+            ..request()
+        };
+        assert!(no_packages.check().is_err());
+        let mut bad_diagnosis = PreauthRequest {
+            diagnoses: vec!["appendicitis".to_string()],
+            ..request()
+        };
+        assert!(bad_diagnosis.check().is_err());
+        assert!(normalize_package_code("1A").is_none());
+        assert_eq!(normalize_package_code(" m1.1 ").as_deref(), Some("M1.1"));
+    }
+}
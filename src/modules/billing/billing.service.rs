@@ -0,0 +1,1429 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{CPT_SYSTEM, HCPCS_SYSTEM, NDC_SYSTEM};
+use crate::modules::billing::billing_contract::{
+    adjusted, expected_payment, reconcile, select_schedule, summarize, AdjustmentGroup, ChargePayment, ChargeVariance,
+    ExpectedPayment, FeeSchedule, FeeScheduleRequest, PaymentRequest, ScheduleRate, VarianceStatus, VarianceSummary,
+};
+use crate::modules::billing::billing_edits::{
+    check_modifiers, pair_findings, CciEdit, EditFinding, EditLine, ModifierIndicator,
+};
+use crate::modules::billing::billing_gst::{
+    financial_year, gstin_state, invoice_number, is_gstin, is_state_code, tax_breakup, DocumentType, GstLine,
+    GstLineRequest, SupplyType,
+};
+use crate::modules::billing::billing_pmjay::{
+    normalize_package_code, preauth_bundle, PmjayConfig, PmjayPackage, PmjayPackageRequest, PreauthRequest,
+};
+use crate::modules::patient::PatientService;
+use crate::standards::terminology::TerminologyService;
+
+// Import SQL queries
+use crate::modules::billing::billing_sql::*;
+
+/// Billing code system a code is looked up in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BillingCodeSystem {
+    Cpt,
+    /// HCPCS Level II
+    Hcpcs,
+    /// National Drug Code, in its 11-digit 5-4-2 form
+    Ndc,
+    /// CPT and HCPCS modifiers
+    Modifier,
+}
+
+impl BillingCodeSystem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillingCodeSystem::Cpt => "cpt",
+            BillingCodeSystem::Hcpcs => "hcpcs",
+            BillingCodeSystem::Ndc => "ndc",
+            BillingCodeSystem::Modifier => "modifier",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "cpt" => Some(BillingCodeSystem::Cpt),
+            "hcpcs" => Some(BillingCodeSystem::Hcpcs),
+            "ndc" => Some(BillingCodeSystem::Ndc),
+            "modifier" => Some(BillingCodeSystem::Modifier),
+            _ => None,
+        }
+    }
+
+    /// Code system URI of the system's codes in FHIR resources
+    pub fn uri(&self) -> Option<&'static str> {
+        match self {
+            BillingCodeSystem::Cpt => Some(CPT_SYSTEM),
+            BillingCodeSystem::Hcpcs => Some(HCPCS_SYSTEM),
+            BillingCodeSystem::Ndc => Some(NDC_SYSTEM),
+            BillingCodeSystem::Modifier => None,
+        }
+    }
+
+    /// A code in the form the system stores it, if it is shaped like one
+    /// of the system's codes
+    pub fn normalize(&self, code: &str) -> Option<String> {
+        let code = code.trim().to_ascii_uppercase();
+        let valid = match self {
+            BillingCodeSystem::Cpt => TerminologyService::is_cpt_code(&code),
+            BillingCodeSystem::Hcpcs => TerminologyService::is_hcpcs_code(&code),
+            BillingCodeSystem::Ndc => return TerminologyService::normalize_ndc(&code),
+            BillingCodeSystem::Modifier => TerminologyService::is_modifier(&code),
+        };
+        valid.then_some(code)
+    }
+}
+
+/// A code of a billing code system
+#[derive(Debug, Clone, Serialize)]
+pub struct BillingCode {
+    pub system: BillingCodeSystem,
+    pub system_uri: Option<&'static str>,
+    pub code: String,
+    pub display: String,
+    pub effective_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
+}
+
+impl BillingCode {
+    /// Whether the code may be billed for services on a date
+    pub fn in_effect(&self, on: NaiveDate) -> bool {
+        self.effective_date <= on && self.termination_date.is_none_or(|terminated| on <= terminated)
+    }
+}
+
+fn billing_code_from_row(row: &PgRow) -> BillingCode {
+    let system = BillingCodeSystem::from_string(row.get("system")).unwrap_or(BillingCodeSystem::Cpt);
+    BillingCode {
+        system,
+        system_uri: system.uri(),
+        code: row.get("code"),
+        display: row.get("display"),
+        effective_date: row.get("effective_date"),
+        termination_date: row.get("termination_date"),
+    }
+}
+
+/// A code as a code set release gives it
+#[derive(Debug, Clone, Deserialize)]
+pub struct BillingCodeRequest {
+    pub code: String,
+    pub display: String,
+    pub effective_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
+}
+
+fn cci_edit_from_row(row: &PgRow) -> CciEdit {
+    CciEdit {
+        column_one: row.get("column_one"),
+        column_two: row.get("column_two"),
+        effective_date: row.get("effective_date"),
+        deletion_date: row.get("deletion_date"),
+        modifier_indicator: ModifierIndicator::from_string(row.get("modifier_indicator"))
+            .unwrap_or(ModifierIndicator::NotAllowed),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChargeStatus {
+    Active,
+    /// Captured in error; kept, but not billed
+    Voided,
+}
+
+impl ChargeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChargeStatus::Active => "active",
+            ChargeStatus::Voided => "voided",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "active" => Some(ChargeStatus::Active),
+            "voided" => Some(ChargeStatus::Voided),
+            _ => None,
+        }
+    }
+}
+
+/// A service captured for billing
+#[derive(Debug, Clone, Serialize)]
+pub struct Charge {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub encounter_id: Uuid,
+    /// `Cpt` or `Hcpcs`
+    pub code_system: BillingCodeSystem,
+    pub code: String,
+    pub modifiers: Vec<String>,
+    /// Drug given, for drug codes
+    pub ndc: Option<String>,
+    pub units: i32,
+    /// Price per unit in the smallest unit of the currency, e.g. cents
+    pub unit_price: i64,
+    pub service_date: NaiveDate,
+    pub status: ChargeStatus,
+    pub captured_by: Option<Uuid>,
+    pub void_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Charge {
+    /// The charge's amount, units times unit price
+    pub fn amount(&self) -> i64 {
+        self.unit_price * i64::from(self.units)
+    }
+
+    fn edit_line(&self) -> EditLine {
+        EditLine {
+            charge_id: Some(self.id),
+            code: self.code.clone(),
+            modifiers: self.modifiers.clone(),
+        }
+    }
+}
+
+fn charge_from_row(row: &PgRow) -> Charge {
+    Charge {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        code_system: BillingCodeSystem::from_string(row.get("code_system")).unwrap_or(BillingCodeSystem::Cpt),
+        code: row.get("code"),
+        modifiers: row.get("modifiers"),
+        ndc: row.get("ndc"),
+        units: row.get("units"),
+        unit_price: row.get("unit_price"),
+        service_date: row.get("service_date"),
+        status: ChargeStatus::from_string(row.get("status")).unwrap_or(ChargeStatus::Active),
+        captured_by: row.get("captured_by"),
+        void_reason: row.get("void_reason"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// A service to capture
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChargeRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Uuid,
+    pub code_system: BillingCodeSystem,
+    pub code: String,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    pub ndc: Option<String>,
+    #[serde(default = "default_units")]
+    pub units: i32,
+    pub unit_price: i64,
+    pub service_date: NaiveDate,
+}
+
+fn default_units() -> i32 {
+    1
+}
+
+impl ChargeRequest {
+    /// Check the request's shape and put its codes in their stored form
+    fn check(&mut self) -> Result<(), String> {
+        if !matches!(self.code_system, BillingCodeSystem::Cpt | BillingCodeSystem::Hcpcs) {
+            return Err("Charges are coded in CPT or HCPCS".to_string());
+        }
+        self.code = self
+            .code_system
+            .normalize(&self.code)
+            .ok_or_else(|| format!("{:?} is not a {} code", self.code, self.code_system.as_str().to_uppercase()))?;
+        self.modifiers = check_modifiers(&self.modifiers)?;
+        if let Some(ndc) = &self.ndc {
+            self.ndc = Some(TerminologyService::normalize_ndc(ndc).ok_or_else(|| format!("{:?} is not an NDC", ndc))?);
+        }
+        if self.units <= 0 {
+            return Err("A charge is for at least one unit".to_string());
+        }
+        if self.unit_price < 0 {
+            return Err("A charge's unit price cannot be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Which charges to list
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChargeQuery {
+    pub encounter_id: Option<Uuid>,
+    pub patient_id: Option<Uuid>,
+    pub status: Option<ChargeStatus>,
+}
+
+/// A GSTIN the tenant supplies under, with its invoice series
+#[derive(Debug, Clone, Serialize)]
+pub struct GstRegistration {
+    pub id: Uuid,
+    pub gstin: String,
+    pub legal_name: String,
+    pub address: Option<String>,
+    pub invoice_prefix: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn gst_registration_from_row(row: &PgRow) -> GstRegistration {
+    GstRegistration {
+        id: row.get("id"),
+        gstin: row.get("gstin"),
+        legal_name: row.get("legal_name"),
+        address: row.get("address"),
+        invoice_prefix: row.get("invoice_prefix"),
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// A GSTIN to register
+#[derive(Debug, Clone, Deserialize)]
+pub struct GstRegistrationRequest {
+    pub gstin: String,
+    pub legal_name: String,
+    pub address: Option<String>,
+    pub invoice_prefix: String,
+}
+
+impl GstRegistrationRequest {
+    /// Check the request's shape and upper-case its GSTIN and prefix
+    fn check(&mut self) -> Result<(), String> {
+        self.gstin = self.gstin.trim().to_ascii_uppercase();
+        if !is_gstin(&self.gstin) {
+            return Err(format!("{:?} is not a GSTIN", self.gstin));
+        }
+        if self.legal_name.trim().is_empty() {
+            return Err("A GST registration needs the holder's legal name".to_string());
+        }
+        self.invoice_prefix = self.invoice_prefix.trim().to_ascii_uppercase();
+        let prefix_valid = (1..=4).contains(&self.invoice_prefix.len())
+            && self.invoice_prefix.chars().all(|c| c.is_ascii_alphanumeric());
+        if !prefix_valid {
+            return Err("An invoice prefix is one to four letters or digits".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Issued,
+    /// Cancelled, e.g. issued in error; its number is not reused
+    Cancelled,
+}
+
+impl InvoiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Issued => "issued",
+            InvoiceStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "issued" => Some(InvoiceStatus::Issued),
+            "cancelled" => Some(InvoiceStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// An invoice issued under a GST registration. Amounts are in paise.
+#[derive(Debug, Clone, Serialize)]
+pub struct GstInvoice {
+    pub id: Uuid,
+    pub registration_id: Uuid,
+    pub invoice_number: String,
+    pub document_type: DocumentType,
+    pub invoice_date: NaiveDate,
+    /// e.g. `2024-25`
+    pub financial_year: String,
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub recipient_name: String,
+    pub recipient_gstin: Option<String>,
+    /// State code of the place of supply
+    pub place_of_supply: String,
+    pub supply_type: SupplyType,
+    pub lines: Vec<GstLine>,
+    pub taxable_value: i64,
+    pub cgst: i64,
+    pub sgst: i64,
+    pub igst: i64,
+    pub total: i64,
+    pub status: InvoiceStatus,
+    pub cancel_reason: Option<String>,
+    pub issued_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn gst_invoice_from_row(row: &PgRow) -> GstInvoice {
+    GstInvoice {
+        id: row.get("id"),
+        registration_id: row.get("registration_id"),
+        invoice_number: row.get("invoice_number"),
+        document_type: DocumentType::from_string(row.get("document_type")).unwrap_or(DocumentType::TaxInvoice),
+        invoice_date: row.get("invoice_date"),
+        financial_year: row.get("financial_year"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        recipient_name: row.get("recipient_name"),
+        recipient_gstin: row.get("recipient_gstin"),
+        place_of_supply: row.get("place_of_supply"),
+        supply_type: SupplyType::from_string(row.get("supply_type")).unwrap_or(SupplyType::IntraState),
+        lines: serde_json::from_value(row.get("lines")).unwrap_or_default(),
+        taxable_value: row.get("taxable_value"),
+        cgst: row.get("cgst"),
+        sgst: row.get("sgst"),
+        igst: row.get("igst"),
+        total: row.get("total"),
+        status: InvoiceStatus::from_string(row.get("status")).unwrap_or(InvoiceStatus::Issued),
+        cancel_reason: row.get("cancel_reason"),
+        issued_by: row.get("issued_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// An invoice to issue
+#[derive(Debug, Clone, Deserialize)]
+pub struct GstInvoiceRequest {
+    pub registration_id: Uuid,
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub invoice_date: NaiveDate,
+    pub recipient_name: String,
+    pub recipient_gstin: Option<String>,
+    /// State code of the place of supply; the recipient's state for a
+    /// registered recipient, otherwise the supplier's
+    pub place_of_supply: Option<String>,
+    pub lines: Vec<GstLineRequest>,
+}
+
+impl GstInvoiceRequest {
+    /// Check the request's shape and upper-case the recipient's GSTIN
+    fn check(&mut self) -> Result<(), String> {
+        if self.recipient_name.trim().is_empty() {
+            return Err("An invoice needs the recipient's name".to_string());
+        }
+        if let Some(gstin) = &self.recipient_gstin {
+            let gstin = gstin.trim().to_ascii_uppercase();
+            if !is_gstin(&gstin) {
+                return Err(format!("{:?} is not a GSTIN", gstin));
+            }
+            self.recipient_gstin = Some(gstin);
+        }
+        if let Some(place) = &self.place_of_supply {
+            if !is_state_code(place.trim()) {
+                return Err(format!("{:?} is not a GST state code", place));
+            }
+            self.place_of_supply = Some(place.trim().to_string());
+        }
+        if self.lines.is_empty() {
+            return Err("An invoice has at least one line".to_string());
+        }
+        self.lines.iter().try_for_each(GstLineRequest::check)
+    }
+}
+
+/// Which invoices to list
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GstInvoiceQuery {
+    pub patient_id: Option<Uuid>,
+    pub registration_id: Option<Uuid>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+fn pmjay_package_from_row(row: &PgRow) -> PmjayPackage {
+    PmjayPackage {
+        code: row.get("code"),
+        specialty: row.get("specialty"),
+        name: row.get("name"),
+        base_rate: row.get("base_rate"),
+        preauth_required: row.get("preauth_required"),
+        day_care: row.get("day_care"),
+        hbp_version: row.get("hbp_version"),
+        effective_date: row.get("effective_date"),
+        termination_date: row.get("termination_date"),
+    }
+}
+
+fn fee_schedule_from_row(row: &PgRow) -> FeeSchedule {
+    FeeSchedule {
+        id: row.get("id"),
+        payer_id: row.get("payer_id"),
+        payer_name: row.get("payer_name"),
+        plan: row.get("plan"),
+        name: row.get("name"),
+        effective_date: row.get("effective_date"),
+        termination_date: row.get("termination_date"),
+        percent_of_charges: row.get("percent_of_charges"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// A rate and the fee schedule it belongs to
+fn schedule_rate_from_row(row: &PgRow) -> (Uuid, ScheduleRate) {
+    let modifier: String = row.get("modifier");
+    let rate = ScheduleRate {
+        code_system: BillingCodeSystem::from_string(row.get("code_system")).unwrap_or(BillingCodeSystem::Cpt),
+        code: row.get("code"),
+        modifier: (!modifier.is_empty()).then_some(modifier),
+        rate: row.get("rate"),
+        effective_date: row.get("effective_date"),
+        termination_date: row.get("termination_date"),
+    };
+    (row.get("fee_schedule_id"), rate)
+}
+
+fn charge_payment_from_row(row: &PgRow) -> ChargePayment {
+    ChargePayment {
+        id: row.get("id"),
+        charge_id: row.get("charge_id"),
+        payer_id: row.get("payer_id"),
+        plan: row.get("plan"),
+        payer_claim_id: row.get("payer_claim_id"),
+        paid_date: row.get("paid_date"),
+        paid: row.get("paid"),
+        patient_responsibility: row.get("patient_responsibility"),
+        adjustments: serde_json::from_value(row.get("adjustments")).unwrap_or_default(),
+        posted_by: row.get("posted_by"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// A posted remittance line, and how the charge's payments by the payer
+/// now compare with its contract
+#[derive(Debug, Clone, Serialize)]
+pub struct PostedPayment {
+    pub payment: ChargePayment,
+    pub variance: ChargeVariance,
+}
+
+/// Which charges' reimbursement to report on: those paid in a period,
+/// optionally by one payer or with one variance status
+#[derive(Debug, Clone, Deserialize)]
+pub struct VarianceQuery {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub payer_id: Option<String>,
+    pub status: Option<VarianceStatus>,
+}
+
+/// Expected against actual reimbursement of the charges paid in a period
+#[derive(Debug, Clone, Serialize)]
+pub struct VarianceReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub summaries: Vec<VarianceSummary>,
+    pub charges: Vec<ChargeVariance>,
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+/// Billing service for code sets, NCCI edits, charge capture, GST invoices
+/// and PM-JAY pre-authorization
+pub struct BillingService {
+    pool: PgPool,
+    patients: Arc<PatientService>,
+    pmjay: PmjayConfig,
+}
+
+impl BillingService {
+    /// Create new billing service
+    pub fn new(pool: PgPool, patients: Arc<PatientService>, pmjay: PmjayConfig) -> Self {
+        Self { pool, patients, pmjay }
+    }
+
+    /// Look a code up, in any of its accepted forms. `None` if the system
+    /// does not have it.
+    pub async fn lookup_code(&self, system: BillingCodeSystem, code: &str) -> Result<Option<BillingCode>, HimsError> {
+        let Some(code) = system.normalize(code) else {
+            return Ok(None);
+        };
+        let row = sqlx::query(GET_BILLING_CODE)
+            .bind(system.as_str())
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(billing_code_from_row))
+    }
+
+    /// Load codes of a code set release, replacing those already loaded.
+    /// Returns how many were loaded.
+    pub async fn import_codes(
+        &self,
+        system: BillingCodeSystem,
+        codes: Vec<BillingCodeRequest>,
+    ) -> Result<usize, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        for request in &codes {
+            let code = system.normalize(&request.code).ok_or_else(|| {
+                validation_error(format!("{:?} is not a {} code", request.code, system.as_str()))
+            })?;
+            if request.termination_date.is_some_and(|terminated| terminated < request.effective_date) {
+                return Err(validation_error(format!("{} is terminated before it takes effect", code)));
+            }
+            sqlx::query(UPSERT_BILLING_CODE)
+                .bind(system.as_str())
+                .bind(&code)
+                .bind(request.display.trim())
+                .bind(request.effective_date)
+                .bind(request.termination_date)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+        Ok(codes.len())
+    }
+
+    /// Load NCCI edits from a CMS release, replacing those already loaded.
+    /// Returns how many were loaded.
+    pub async fn import_cci_edits(&self, edits: Vec<CciEdit>) -> Result<usize, HimsError> {
+        let procedure = |code: &str| TerminologyService::is_cpt_code(code) || TerminologyService::is_hcpcs_code(code);
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        for edit in &edits {
+            if !procedure(&edit.column_one) || !procedure(&edit.column_two) {
+                return Err(validation_error(format!(
+                    "NCCI edit {}/{} is not between CPT or HCPCS codes",
+                    edit.column_one, edit.column_two
+                )));
+            }
+            sqlx::query(UPSERT_CCI_EDIT)
+                .bind(&edit.column_one)
+                .bind(&edit.column_two)
+                .bind(edit.effective_date)
+                .bind(edit.deletion_date)
+                .bind(edit.modifier_indicator.as_str())
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+        Ok(edits.len())
+    }
+
+    async fn check_encounter(conn: &mut PgConnection, encounter_id: Uuid, patient_id: Uuid) -> Result<(), HimsError> {
+        let subject: Option<Uuid> = sqlx::query(GET_ENCOUNTER_SUBJECT)
+            .bind(encounter_id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?
+            .map(|row| row.get("subject"));
+        match subject {
+            Some(subject) if subject == patient_id => Ok(()),
+            Some(_) => Err(validation_error(format!(
+                "Encounter {} is not of patient {}",
+                encounter_id, patient_id
+            ))),
+            None => Err(validation_error(format!("Encounter {} does not exist", encounter_id))),
+        }
+    }
+
+    /// Check codes are in a system and billable on a date of service
+    async fn check_codes(
+        conn: &mut PgConnection,
+        system: BillingCodeSystem,
+        codes: &[String],
+        on: NaiveDate,
+    ) -> Result<(), HimsError> {
+        if codes.is_empty() {
+            return Ok(());
+        }
+        let known: Vec<BillingCode> = sqlx::query(GET_BILLING_CODES)
+            .bind(system.as_str())
+            .bind(codes)
+            .fetch_all(conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(billing_code_from_row)
+            .collect();
+        for code in codes {
+            let name = system.as_str().to_uppercase();
+            match known.iter().find(|known| &known.code == code) {
+                Some(known) if known.in_effect(on) => {}
+                Some(_) => return Err(validation_error(format!("{} {} is not billable on {}", name, code, on))),
+                None => return Err(validation_error(format!("{} {} is not a known code", name, code))),
+            }
+        }
+        Ok(())
+    }
+
+    async fn edits_among(conn: &mut PgConnection, codes: Vec<String>) -> Result<Vec<CciEdit>, HimsError> {
+        let rows = sqlx::query(GET_CCI_EDITS_AMONG)
+            .bind(codes)
+            .fetch_all(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(cci_edit_from_row).collect())
+    }
+
+    /// Capture a charge once its codes are checked: known and billable on
+    /// the date of service, and allowed by NCCI edits with the patient's
+    /// other charges that day
+    pub async fn capture_charge(
+        &self,
+        mut request: ChargeRequest,
+        captured_by: Option<Uuid>,
+    ) -> Result<Charge, HimsError> {
+        request.check().map_err(validation_error)?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        Self::check_encounter(&mut tx, request.encounter_id, request.patient_id).await?;
+        let code = std::slice::from_ref(&request.code);
+        Self::check_codes(&mut tx, request.code_system, code, request.service_date).await?;
+        Self::check_codes(&mut tx, BillingCodeSystem::Modifier, &request.modifiers, request.service_date).await?;
+        if let Some(ndc) = &request.ndc {
+            Self::check_codes(&mut tx, BillingCodeSystem::Ndc, std::slice::from_ref(ndc), request.service_date).await?;
+        }
+
+        let day: Vec<Charge> = sqlx::query(LOCK_DAY_CHARGES)
+            .bind(request.patient_id)
+            .bind(request.service_date)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(charge_from_row)
+            .collect();
+        let mut lines: Vec<EditLine> = day.iter().map(Charge::edit_line).collect();
+        lines.push(EditLine {
+            charge_id: None,
+            code: request.code.clone(),
+            modifiers: request.modifiers.clone(),
+        });
+        let edits = Self::edits_among(&mut tx, lines.iter().map(|line| line.code.clone()).collect()).await?;
+        let rejected: Vec<String> = pair_findings(&lines, &edits, request.service_date)
+            .into_iter()
+            .filter(|finding| finding.column_one_charge.is_none() || finding.column_two_charge.is_none())
+            .map(|finding| finding.message)
+            .collect();
+        if !rejected.is_empty() {
+            return Err(validation_error(format!("NCCI edit: {}", rejected.join("; "))));
+        }
+
+        let row = sqlx::query(INSERT_CHARGE)
+            .bind(Uuid::new_v4())
+            .bind(request.patient_id)
+            .bind(request.encounter_id)
+            .bind(request.code_system.as_str())
+            .bind(&request.code)
+            .bind(&request.modifiers)
+            .bind(&request.ndc)
+            .bind(request.units)
+            .bind(request.unit_price)
+            .bind(request.service_date)
+            .bind(captured_by)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(charge_from_row(&row))
+    }
+
+    /// Get charge by ID
+    pub async fn get_charge(&self, id: Uuid) -> Result<Option<Charge>, HimsError> {
+        let row = sqlx::query(GET_CHARGE_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(charge_from_row))
+    }
+
+    /// Up to 1000 charges matching a query, by date of service
+    pub async fn list_charges(&self, query: &ChargeQuery) -> Result<Vec<Charge>, HimsError> {
+        let rows = sqlx::query(LIST_CHARGES)
+            .bind(query.encounter_id)
+            .bind(query.patient_id)
+            .bind(query.status.map(|status| status.as_str()))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(charge_from_row).collect())
+    }
+
+    /// Void a charge captured in error. `None` if there is no such charge.
+    pub async fn void_charge(&self, id: Uuid, reason: String) -> Result<Option<Charge>, HimsError> {
+        if reason.trim().is_empty() {
+            return Err(validation_error("Voiding a charge needs a reason".to_string()));
+        }
+        let row = sqlx::query(VOID_CHARGE)
+            .bind(id)
+            .bind(reason.trim())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        match row {
+            Some(row) => Ok(Some(charge_from_row(&row))),
+            None => match self.get_charge(id).await? {
+                Some(_) => Err(HimsError::ConflictError {
+                    message: format!("Charge {} is already voided", id),
+                }),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// NCCI edits a patient's active charges on a date of service fail, to
+    /// clear before the claim is submitted, e.g. after edits were updated
+    pub async fn check_edits(
+        &self,
+        patient_id: Uuid,
+        service_date: NaiveDate,
+    ) -> Result<Vec<EditFinding>, HimsError> {
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        let lines: Vec<EditLine> = sqlx::query(GET_DAY_CHARGES)
+            .bind(patient_id)
+            .bind(service_date)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(|row| charge_from_row(row).edit_line())
+            .collect();
+        let edits = Self::edits_among(&mut conn, lines.iter().map(|line| line.code.clone()).collect()).await?;
+        Ok(pair_findings(&lines, &edits, service_date))
+    }
+
+    /// Register a GSTIN the tenant supplies under
+    pub async fn create_registration(&self, mut request: GstRegistrationRequest) -> Result<GstRegistration, HimsError> {
+        request.check().map_err(validation_error)?;
+        let row = sqlx::query(INSERT_GST_REGISTRATION)
+            .bind(Uuid::new_v4())
+            .bind(&request.gstin)
+            .bind(request.legal_name.trim())
+            .bind(&request.address)
+            .bind(&request.invoice_prefix)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("GSTIN {} is already registered", request.gstin)))?;
+        Ok(gst_registration_from_row(&row))
+    }
+
+    /// The tenant's GST registrations
+    pub async fn list_registrations(&self) -> Result<Vec<GstRegistration>, HimsError> {
+        let rows = sqlx::query(LIST_GST_REGISTRATIONS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(gst_registration_from_row).collect())
+    }
+
+    /// Issue an invoice under a registration, numbered next in its series
+    /// for the financial year, with the tax on each line split between CGST
+    /// and SGST, or charged as IGST, by the place of supply
+    pub async fn issue_invoice(
+        &self,
+        mut request: GstInvoiceRequest,
+        issued_by: Option<Uuid>,
+    ) -> Result<GstInvoice, HimsError> {
+        request.check().map_err(validation_error)?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let registration = sqlx::query(GET_GST_REGISTRATION)
+            .bind(request.registration_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .map(|row| gst_registration_from_row(&row))
+            .filter(|registration| registration.active)
+            .ok_or_else(|| {
+                validation_error(format!("GST registration {} does not exist or is inactive", request.registration_id))
+            })?;
+        if let Some(encounter_id) = request.encounter_id {
+            Self::check_encounter(&mut tx, encounter_id, request.patient_id).await?;
+        }
+
+        let supplier_state = gstin_state(&registration.gstin);
+        let place_of_supply = request
+            .place_of_supply
+            .clone()
+            .or_else(|| request.recipient_gstin.as_deref().map(|gstin| gstin_state(gstin).to_string()))
+            .unwrap_or_else(|| supplier_state.to_string());
+        let supply_type = SupplyType::between(supplier_state, &place_of_supply);
+        let (lines, totals) = tax_breakup(&request.lines, supply_type);
+        let document_type = if lines.iter().all(|line| line.gst_rate == 0.0) {
+            DocumentType::BillOfSupply
+        } else {
+            DocumentType::TaxInvoice
+        };
+
+        let year = financial_year(request.invoice_date);
+        let number: i32 = sqlx::query(NEXT_INVOICE_NUMBER)
+            .bind(registration.id)
+            .bind(&year)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .get("number");
+        let invoice_number = invoice_number(&registration.invoice_prefix, &year, number).ok_or_else(|| {
+            HimsError::ConflictError {
+                message: format!("The {} invoice series of {} is exhausted", year, registration.gstin),
+            }
+        })?;
+
+        let row = sqlx::query(INSERT_GST_INVOICE)
+            .bind(Uuid::new_v4())
+            .bind(registration.id)
+            .bind(&invoice_number)
+            .bind(document_type.as_str())
+            .bind(request.invoice_date)
+            .bind(&year)
+            .bind(request.patient_id)
+            .bind(request.encounter_id)
+            .bind(request.recipient_name.trim())
+            .bind(&request.recipient_gstin)
+            .bind(&place_of_supply)
+            .bind(supply_type.as_str())
+            .bind(serde_json::to_value(&lines).unwrap_or_default())
+            .bind(totals.taxable_value)
+            .bind(totals.cgst)
+            .bind(totals.sgst)
+            .bind(totals.igst)
+            .bind(totals.total)
+            .bind(issued_by)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| write_error(e, || format!("Invoice {} is already issued", invoice_number)))?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(gst_invoice_from_row(&row))
+    }
+
+    /// Get invoice by ID
+    pub async fn get_invoice(&self, id: Uuid) -> Result<Option<GstInvoice>, HimsError> {
+        let row = sqlx::query(GET_GST_INVOICE_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(gst_invoice_from_row))
+    }
+
+    /// Up to 1000 invoices matching a query, by series and number
+    pub async fn list_invoices(&self, query: &GstInvoiceQuery) -> Result<Vec<GstInvoice>, HimsError> {
+        let rows = sqlx::query(LIST_GST_INVOICES)
+            .bind(query.patient_id)
+            .bind(query.registration_id)
+            .bind(query.from)
+            .bind(query.to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(gst_invoice_from_row).collect())
+    }
+
+    /// Cancel an invoice; its number stays taken. `None` if there is no
+    /// such invoice.
+    pub async fn cancel_invoice(&self, id: Uuid, reason: String) -> Result<Option<GstInvoice>, HimsError> {
+        if reason.trim().is_empty() {
+            return Err(validation_error("Cancelling an invoice needs a reason".to_string()));
+        }
+        let row = sqlx::query(CANCEL_GST_INVOICE)
+            .bind(id)
+            .bind(reason.trim())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        match row {
+            Some(row) => Ok(Some(gst_invoice_from_row(&row))),
+            None => match self.get_invoice(id).await? {
+                Some(_) => Err(HimsError::ConflictError {
+                    message: format!("Invoice {} is already cancelled", id),
+                }),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Load packages of the PM-JAY package master, replacing those already
+    /// loaded. Returns how many were loaded.
+    pub async fn import_packages(&self, packages: Vec<PmjayPackageRequest>) -> Result<usize, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        for request in &packages {
+            let code = normalize_package_code(&request.code)
+                .ok_or_else(|| validation_error(format!("{:?} is not a PM-JAY package code", request.code)))?;
+            if request.base_rate < 0 {
+                return Err(validation_error(format!("Package {} has a negative rate", code)));
+            }
+            if request.termination_date.is_some_and(|terminated| terminated < request.effective_date) {
+                return Err(validation_error(format!("Package {} is terminated before it takes effect", code)));
+            }
+            sqlx::query(UPSERT_PMJAY_PACKAGE)
+                .bind(&code)
+                .bind(request.specialty.trim())
+                .bind(request.name.trim())
+                .bind(request.base_rate)
+                .bind(request.preauth_required)
+                .bind(request.day_care)
+                .bind(request.hbp_version.trim())
+                .bind(request.effective_date)
+                .bind(request.termination_date)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+        Ok(packages.len())
+    }
+
+    /// Look a package up. `None` if the package master does not have it.
+    pub async fn get_package(&self, code: &str) -> Result<Option<PmjayPackage>, HimsError> {
+        let Some(code) = normalize_package_code(code) else {
+            return Ok(None);
+        };
+        let row = sqlx::query(GET_PMJAY_PACKAGE)
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(pmjay_package_from_row))
+    }
+
+    /// Up to 1000 packages, optionally of one specialty, by code
+    pub async fn list_packages(&self, specialty: Option<&str>) -> Result<Vec<PmjayPackage>, HimsError> {
+        let rows = sqlx::query(LIST_PMJAY_PACKAGES)
+            .bind(specialty)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(pmjay_package_from_row).collect())
+    }
+
+    /// A pre-authorization request for packages, as the FHIR Bundle the
+    /// National Health Claims Exchange takes, once the packages are checked
+    /// to be in the package master on the admission date
+    pub async fn preauth_payload(&self, mut request: PreauthRequest) -> Result<Value, HimsError> {
+        request.check().map_err(validation_error)?;
+
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        Self::check_encounter(&mut conn, request.encounter_id, request.patient_id).await?;
+        let known: Vec<PmjayPackage> = sqlx::query(GET_PMJAY_PACKAGES)
+            .bind(&request.package_codes)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(pmjay_package_from_row)
+            .collect();
+        let mut packages = Vec::new();
+        for code in &request.package_codes {
+            match known.iter().find(|package| &package.code == code) {
+                Some(package) if package.in_effect(request.admission_date) => packages.push(package.clone()),
+                Some(_) => {
+                    return Err(validation_error(format!(
+                        "Package {} is not in effect on {}",
+                        code, request.admission_date
+                    )))
+                }
+                None => return Err(validation_error(format!("Package {} is not in the package master", code))),
+            }
+        }
+
+        let patient = self
+            .patients
+            .get_patient(request.patient_id)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| validation_error(format!("Patient {} does not exist", request.patient_id)))?;
+        Ok(preauth_bundle(Uuid::new_v4(), &request, &packages, patient, &self.pmjay, Utc::now()))
+    }
+
+    /// Add a fee schedule to a payer's contract
+    pub async fn create_fee_schedule(&self, request: FeeScheduleRequest) -> Result<FeeSchedule, HimsError> {
+        request.check().map_err(validation_error)?;
+        let plan = request.plan.as_deref().map(str::trim).filter(|plan| !plan.is_empty());
+        let row = sqlx::query(INSERT_FEE_SCHEDULE)
+            .bind(Uuid::new_v4())
+            .bind(request.payer_id.trim())
+            .bind(request.payer_name.trim())
+            .bind(plan)
+            .bind(request.name.trim())
+            .bind(request.effective_date)
+            .bind(request.termination_date)
+            .bind(request.percent_of_charges)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| {
+                write_error(e, || {
+                    format!("Payer {} already has a fee schedule named {}", request.payer_id, request.name)
+                })
+            })?;
+        Ok(fee_schedule_from_row(&row))
+    }
+
+    /// Get fee schedule by ID
+    pub async fn get_fee_schedule(&self, id: Uuid) -> Result<Option<FeeSchedule>, HimsError> {
+        let row = sqlx::query(GET_FEE_SCHEDULE_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(fee_schedule_from_row))
+    }
+
+    /// Fee schedules, optionally of one payer
+    pub async fn list_fee_schedules(&self, payer_id: Option<&str>) -> Result<Vec<FeeSchedule>, HimsError> {
+        let rows = sqlx::query(LIST_FEE_SCHEDULES)
+            .bind(payer_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(fee_schedule_from_row).collect())
+    }
+
+    /// Load rates into a fee schedule, replacing those with the same code,
+    /// modifier and effective date. Returns how many were loaded, `None` if
+    /// there is no such schedule.
+    pub async fn import_rates(&self, id: Uuid, rates: Vec<ScheduleRate>) -> Result<Option<usize>, HimsError> {
+        if self.get_fee_schedule(id).await?.is_none() {
+            return Ok(None);
+        }
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        for rate in &rates {
+            if !matches!(rate.code_system, BillingCodeSystem::Cpt | BillingCodeSystem::Hcpcs) {
+                return Err(validation_error("Fee schedules price CPT or HCPCS codes".to_string()));
+            }
+            let code = rate.code_system.normalize(&rate.code).ok_or_else(|| {
+                validation_error(format!("{:?} is not a {} code", rate.code, rate.code_system.as_str()))
+            })?;
+            let modifier = match &rate.modifier {
+                Some(modifier) => BillingCodeSystem::Modifier
+                    .normalize(modifier)
+                    .ok_or_else(|| validation_error(format!("{:?} is not a modifier", modifier)))?,
+                None => String::new(),
+            };
+            if rate.rate < 0 {
+                return Err(validation_error(format!("The rate of {} cannot be negative", code)));
+            }
+            if rate.termination_date.is_some_and(|terminated| terminated < rate.effective_date) {
+                return Err(validation_error(format!("The rate of {} ends before it takes effect", code)));
+            }
+            sqlx::query(UPSERT_SCHEDULE_RATE)
+                .bind(id)
+                .bind(rate.code_system.as_str())
+                .bind(&code)
+                .bind(&modifier)
+                .bind(rate.rate)
+                .bind(rate.effective_date)
+                .bind(rate.termination_date)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+        Ok(Some(rates.len()))
+    }
+
+    /// A fee schedule's rates, by code
+    pub async fn list_rates(&self, id: Uuid) -> Result<Vec<ScheduleRate>, HimsError> {
+        let rows = sqlx::query(LIST_SCHEDULE_RATES)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(|row| schedule_rate_from_row(row).1).collect())
+    }
+
+    /// Expected payments of charges, each under the contract of the payer
+    /// and plan it is paired with
+    async fn expected_payments(
+        conn: &mut PgConnection,
+        charges: &[(&Charge, &str, Option<&str>)],
+    ) -> Result<Vec<ExpectedPayment>, HimsError> {
+        let mut payer_ids: Vec<String> = charges.iter().map(|(_, payer_id, _)| payer_id.to_string()).collect();
+        payer_ids.sort();
+        payer_ids.dedup();
+        let schedules: Vec<FeeSchedule> = sqlx::query(GET_PAYER_FEE_SCHEDULES)
+            .bind(&payer_ids)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(fee_schedule_from_row)
+            .collect();
+        let schedule_ids: Vec<Uuid> = schedules.iter().map(|schedule| schedule.id).collect();
+        let codes: Vec<String> = charges.iter().map(|(charge, _, _)| charge.code.clone()).collect();
+        let rates: Vec<(Uuid, ScheduleRate)> = sqlx::query(GET_SCHEDULE_RATES_AMONG)
+            .bind(&schedule_ids)
+            .bind(&codes)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(schedule_rate_from_row)
+            .collect();
+
+        Ok(charges
+            .iter()
+            .map(|(charge, payer_id, plan)| {
+                let schedule = select_schedule(&schedules, payer_id, *plan, charge.service_date);
+                let schedule_rates: Vec<ScheduleRate> = rates
+                    .iter()
+                    .filter(|(id, _)| schedule.is_some_and(|schedule| schedule.id == *id))
+                    .map(|(_, rate)| rate.clone())
+                    .collect();
+                expected_payment(schedule, &schedule_rates, charge)
+            })
+            .collect())
+    }
+
+    /// What a payer's contract says to expect for a charge. `None` if there
+    /// is no such charge.
+    pub async fn expected_for_charge(
+        &self,
+        charge_id: Uuid,
+        payer_id: &str,
+        plan: Option<&str>,
+    ) -> Result<Option<ExpectedPayment>, HimsError> {
+        let Some(charge) = self.get_charge(charge_id).await? else {
+            return Ok(None);
+        };
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        let mut expected = Self::expected_payments(&mut conn, &[(&charge, payer_id, plan)]).await?;
+        Ok(expected.pop())
+    }
+
+    /// Post a remittance line against a charge, as the 835 posting workflow
+    /// does for each service line, and compare the charge's payments by the
+    /// payer with its contract
+    pub async fn post_payment(
+        &self,
+        request: PaymentRequest,
+        posted_by: Option<Uuid>,
+    ) -> Result<PostedPayment, HimsError> {
+        request.check().map_err(validation_error)?;
+        let payer_id = request.payer_id.trim();
+        let plan = request.plan.as_deref().map(str::trim).filter(|plan| !plan.is_empty());
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let charge = sqlx::query(GET_CHARGE_BY_ID)
+            .bind(request.charge_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .map(|row| charge_from_row(&row))
+            .ok_or_else(|| validation_error(format!("Charge {} does not exist", request.charge_id)))?;
+        let row = sqlx::query(INSERT_CHARGE_PAYMENT)
+            .bind(Uuid::new_v4())
+            .bind(charge.id)
+            .bind(payer_id)
+            .bind(plan)
+            .bind(&request.payer_claim_id)
+            .bind(request.paid_date)
+            .bind(request.paid)
+            .bind(adjusted(&request.adjustments, AdjustmentGroup::PatientResponsibility))
+            .bind(serde_json::to_value(&request.adjustments).unwrap_or_default())
+            .bind(posted_by)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        let payment = charge_payment_from_row(&row);
+
+        let payments: Vec<ChargePayment> = sqlx::query(GET_CHARGE_PAYMENTS)
+            .bind(vec![charge.id])
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(charge_payment_from_row)
+            .collect();
+        let by_payer: Vec<&ChargePayment> =
+            payments.iter().filter(|other| other.payer_id == payer_id && other.plan.as_deref() == plan).collect();
+        let expected = Self::expected_payments(&mut tx, &[(&charge, payer_id, plan)]).await?;
+        let variance = reconcile(&charge, &by_payer, &expected[0]);
+        tx.commit().await.map_err(database_error)?;
+
+        if variance.status == VarianceStatus::Underpaid {
+            tracing::warn!(
+                "Charge {} is underpaid by payer {}: {:?} against {:?} expected",
+                charge.id,
+                payer_id,
+                variance.actual,
+                variance.expected
+            );
+        }
+        Ok(PostedPayment { payment, variance })
+    }
+
+    /// Remittance lines posted against a charge
+    pub async fn list_payments(&self, charge_id: Uuid) -> Result<Vec<ChargePayment>, HimsError> {
+        let rows = sqlx::query(GET_CHARGE_PAYMENTS)
+            .bind(vec![charge_id])
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(charge_payment_from_row).collect())
+    }
+
+    /// Expected against actual reimbursement of the charges paid in a
+    /// period, each payer's payments for a charge taken together, those
+    /// posted outside the period included, with a summary per payer
+    pub async fn variance_report(&self, query: &VarianceQuery) -> Result<VarianceReport, HimsError> {
+        if query.to < query.from {
+            return Err(validation_error("A report's period cannot end before it starts".to_string()));
+        }
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        let charge_ids: Vec<Uuid> = sqlx::query(GET_PAID_CHARGE_IDS)
+            .bind(query.from)
+            .bind(query.to)
+            .bind(query.payer_id.as_deref())
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(|row| row.get("charge_id"))
+            .collect();
+        let charges: Vec<Charge> = sqlx::query(GET_CHARGES_AMONG)
+            .bind(&charge_ids)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(charge_from_row)
+            .collect();
+        let payments: Vec<ChargePayment> = sqlx::query(GET_CHARGE_PAYMENTS)
+            .bind(&charge_ids)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(charge_payment_from_row)
+            .collect();
+
+        // Each charge's payments, a group per payer and plan
+        let mut groups: Vec<(&Charge, Vec<&ChargePayment>)> = Vec::new();
+        for payment in &payments {
+            if query.payer_id.as_deref().is_some_and(|payer_id| payer_id != payment.payer_id) {
+                continue;
+            }
+            let Some(charge) = charges.iter().find(|charge| charge.id == payment.charge_id) else {
+                continue;
+            };
+            let group = groups.iter_mut().find(|(other, group)| {
+                other.id == charge.id && group[0].payer_id == payment.payer_id && group[0].plan == payment.plan
+            });
+            match group {
+                Some((_, group)) => group.push(payment),
+                None => groups.push((charge, vec![payment])),
+            }
+        }
+        let priced: Vec<(&Charge, &str, Option<&str>)> = groups
+            .iter()
+            .map(|(charge, group)| (*charge, group[0].payer_id.as_str(), group[0].plan.as_deref()))
+            .collect();
+        let expected = Self::expected_payments(&mut conn, &priced).await?;
+
+        let mut variances: Vec<ChargeVariance> = groups
+            .iter()
+            .zip(&expected)
+            .map(|((charge, group), expected)| reconcile(charge, group, expected))
+            .filter(|variance| query.status.is_none_or(|status| variance.status == status))
+            .collect();
+        variances.sort_by(|a, b| (&a.payer_id, a.service_date).cmp(&(&b.payer_id, b.service_date)));
+        Ok(VarianceReport {
+            from: query.from,
+            to: query.to,
+            summaries: summarize(&variances),
+            charges: variances,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> ChargeRequest {
+        ChargeRequest {
+            patient_id: Uuid::new_v4(),
+            encounter_id: Uuid::new_v4(),
+            code_system: BillingCodeSystem::Hcpcs,
+            code: " j1100 ".to_string(),
+            modifiers: vec!["jw".to_string()],
+            ndc: Some("0009-0047-01".to_string()),
+            units: 2,
+            unit_price: 1250,
+            service_date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_charge_request_check() {
+        let mut charge = request();
+        charge.check().unwrap();
+        assert_eq!(charge.code, "J1100");
+        assert_eq!(charge.modifiers, vec!["JW"]);
+        assert_eq!(charge.ndc.as_deref(), Some("00009004701"));
+
+        let mut cpt_shaped = ChargeRequest {
+            code: "99213".to_string(),
+            ..request()
+        };
+        assert!(cpt_shaped.check().is_err());
+        let mut ndc_coded = ChargeRequest {
+            code_system: BillingCodeSystem::Ndc,
+            ..request()
+        };
+        assert!(ndc_coded.check().is_err());
+        let mut no_units = ChargeRequest { units: 0, ..request() };
+        assert!(no_units.check().is_err());
+    }
+
+    #[test]
+    fn test_billing_code_in_effect() {
+        let code = BillingCode {
+            system: BillingCodeSystem::Cpt,
+            system_uri: Some(CPT_SYSTEM),
+            code: "99213".to_string(),
+            display: "Office visit".to_string(),
+            effective_date: NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+            termination_date: NaiveDate::from_ymd_opt(2023, 12, 31),
+        };
+        assert!(code.in_effect(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()));
+        assert!(!code.in_effect(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()));
+        assert!(!code.in_effect(NaiveDate::from_ymd_opt(2020, 12, 31).unwrap()));
+        assert_eq!(BillingCodeSystem::Ndc.normalize("0009-0047-01").as_deref(), Some("00009004701"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Billing SQL Queries
+//!
+//! This file contains all SQL queries used by the billing service.
+
+/// Get a code of a billing code system
+pub const GET_BILLING_CODE: &str = r#"
+    SELECT system, code, display, effective_date, termination_date
+    FROM billing_codes
+    WHERE system = $1 AND code = $2
+"#;
+
+/// Codes of a billing code system among $2
+pub const GET_BILLING_CODES: &str = r#"
+    SELECT system, code, display, effective_date, termination_date
+    FROM billing_codes
+    WHERE system = $1 AND code = ANY($2)
+"#;
+
+/// Add a code to a billing code system, or replace it
+pub const UPSERT_BILLING_CODE: &str = r#"
+    INSERT INTO billing_codes (system, code, display, effective_date, termination_date)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (system, code) DO UPDATE
+    SET display = EXCLUDED.display, effective_date = EXCLUDED.effective_date,
+        termination_date = EXCLUDED.termination_date, updated_at = NOW()
+"#;
+
+/// Add an NCCI edit, or replace its deletion date and modifier indicator
+pub const UPSERT_CCI_EDIT: &str = r#"
+    INSERT INTO cci_edits (column_one, column_two, effective_date, deletion_date, modifier_indicator)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (column_one, column_two, effective_date) DO UPDATE
+    SET deletion_date = EXCLUDED.deletion_date, modifier_indicator = EXCLUDED.modifier_indicator
+"#;
+
+/// NCCI edits between codes among $1
+pub const GET_CCI_EDITS_AMONG: &str = r#"
+    SELECT column_one, column_two, effective_date, deletion_date, modifier_indicator
+    FROM cci_edits
+    WHERE column_one = ANY($1) AND column_two = ANY($1)
+"#;
+
+/// Get encounter subject for charge capture
+pub const GET_ENCOUNTER_SUBJECT: &str = r#"
+    SELECT subject
+    FROM encounters
+    WHERE id = $1
+"#;
+
+/// Capture a charge
+pub const INSERT_CHARGE: &str = r#"
+    INSERT INTO charges (id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price,
+                         service_date, captured_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+    RETURNING id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price, service_date,
+              status, captured_by, void_reason, created_at, updated_at
+"#;
+
+/// Get charge by ID
+pub const GET_CHARGE_BY_ID: &str = r#"
+    SELECT id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price, service_date,
+           status, captured_by, void_reason, created_at, updated_at
+    FROM charges
+    WHERE id = $1
+"#;
+
+/// Charges, optionally of one encounter ($1) or patient ($2), or in one
+/// status ($3); by date of service
+pub const LIST_CHARGES: &str = r#"
+    SELECT id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price, service_date,
+           status, captured_by, void_reason, created_at, updated_at
+    FROM charges
+    WHERE ($1::uuid IS NULL OR encounter_id = $1)
+      AND ($2::uuid IS NULL OR patient_id = $2)
+      AND ($3::text IS NULL OR status = $3)
+    ORDER BY service_date, created_at
+    LIMIT 1000
+"#;
+
+/// A patient's active charges on a date of service, locked while another
+/// is checked against them
+pub const LOCK_DAY_CHARGES: &str = r#"
+    SELECT id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price, service_date,
+           status, captured_by, void_reason, created_at, updated_at
+    FROM charges
+    WHERE patient_id = $1 AND service_date = $2 AND status = 'active'
+    FOR UPDATE
+"#;
+
+/// A patient's active charges on a date of service
+pub const GET_DAY_CHARGES: &str = r#"
+    SELECT id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price, service_date,
+           status, captured_by, void_reason, created_at, updated_at
+    FROM charges
+    WHERE patient_id = $1 AND service_date = $2 AND status = 'active'
+"#;
+
+/// Void an active charge
+pub const VOID_CHARGE: &str = r#"
+    UPDATE charges
+    SET status = 'voided', void_reason = $2
+    WHERE id = $1 AND status = 'active'
+    RETURNING id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price, service_date,
+              status, captured_by, void_reason, created_at, updated_at
+"#;
+
+/// Register a GSTIN of the tenant's
+pub const INSERT_GST_REGISTRATION: &str = r#"
+    INSERT INTO gst_registrations (id, gstin, legal_name, address, invoice_prefix)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING id, gstin, legal_name, address, invoice_prefix, active, created_at, updated_at
+"#;
+
+/// The tenant's GST registrations
+pub const LIST_GST_REGISTRATIONS: &str = r#"
+    SELECT id, gstin, legal_name, address, invoice_prefix, active, created_at, updated_at
+    FROM gst_registrations
+    ORDER BY gstin
+"#;
+
+/// Get GST registration by ID
+pub const GET_GST_REGISTRATION: &str = r#"
+    SELECT id, gstin, legal_name, address, invoice_prefix, active, created_at, updated_at
+    FROM gst_registrations
+    WHERE id = $1
+"#;
+
+/// Take the next number of a registration's invoice series in a financial
+/// year; the series row stays locked until the invoice is issued
+pub const NEXT_INVOICE_NUMBER: &str = r#"
+    INSERT INTO gst_invoice_series (registration_id, financial_year, next_number)
+    VALUES ($1, $2, 2)
+    ON CONFLICT (registration_id, financial_year) DO UPDATE
+    SET next_number = gst_invoice_series.next_number + 1
+    RETURNING next_number - 1 AS number
+"#;
+
+/// Issue an invoice
+pub const INSERT_GST_INVOICE: &str = r#"
+    INSERT INTO gst_invoices (id, registration_id, invoice_number, document_type, invoice_date, financial_year,
+                              patient_id, encounter_id, recipient_name, recipient_gstin, place_of_supply,
+                              supply_type, lines, taxable_value, cgst, sgst, igst, total, issued_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+    RETURNING id, registration_id, invoice_number, document_type, invoice_date, financial_year, patient_id,
+              encounter_id, recipient_name, recipient_gstin, place_of_supply, supply_type, lines, taxable_value,
+              cgst, sgst, igst, total, status, cancel_reason, issued_by, created_at, updated_at
+"#;
+
+/// Get invoice by ID
+pub const GET_GST_INVOICE_BY_ID: &str = r#"
+    SELECT id, registration_id, invoice_number, document_type, invoice_date, financial_year, patient_id,
+           encounter_id, recipient_name, recipient_gstin, place_of_supply, supply_type, lines, taxable_value,
+           cgst, sgst, igst, total, status, cancel_reason, issued_by, created_at, updated_at
+    FROM gst_invoices
+    WHERE id = $1
+"#;
+
+/// Invoices, optionally of one patient ($1) or registration ($2), or
+/// issued from ($3) or to ($4) a date; by invoice number
+pub const LIST_GST_INVOICES: &str = r#"
+    SELECT id, registration_id, invoice_number, document_type, invoice_date, financial_year, patient_id,
+           encounter_id, recipient_name, recipient_gstin, place_of_supply, supply_type, lines, taxable_value,
+           cgst, sgst, igst, total, status, cancel_reason, issued_by, created_at, updated_at
+    FROM gst_invoices
+    WHERE ($1::uuid IS NULL OR patient_id = $1)
+      AND ($2::uuid IS NULL OR registration_id = $2)
+      AND ($3::date IS NULL OR invoice_date >= $3)
+      AND ($4::date IS NULL OR invoice_date <= $4)
+    ORDER BY registration_id, invoice_date, invoice_number
+    LIMIT 1000
+"#;
+
+/// Cancel an issued invoice
+pub const CANCEL_GST_INVOICE: &str = r#"
+    UPDATE gst_invoices
+    SET status = 'cancelled', cancel_reason = $2
+    WHERE id = $1 AND status = 'issued'
+    RETURNING id, registration_id, invoice_number, document_type, invoice_date, financial_year, patient_id,
+              encounter_id, recipient_name, recipient_gstin, place_of_supply, supply_type, lines, taxable_value,
+              cgst, sgst, igst, total, status, cancel_reason, issued_by, created_at, updated_at
+"#;
+
+/// Add a package to the PM-JAY package master, or replace it
+pub const UPSERT_PMJAY_PACKAGE: &str = r#"
+    INSERT INTO pmjay_packages (code, specialty, name, base_rate, preauth_required, day_care, hbp_version,
+                                effective_date, termination_date)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (code) DO UPDATE
+    SET specialty = EXCLUDED.specialty, name = EXCLUDED.name, base_rate = EXCLUDED.base_rate,
+        preauth_required = EXCLUDED.preauth_required, day_care = EXCLUDED.day_care,
+        hbp_version = EXCLUDED.hbp_version, effective_date = EXCLUDED.effective_date,
+        termination_date = EXCLUDED.termination_date, updated_at = NOW()
+"#;
+
+/// Get PM-JAY package by code
+pub const GET_PMJAY_PACKAGE: &str = r#"
+    SELECT code, specialty, name, base_rate, preauth_required, day_care, hbp_version, effective_date,
+           termination_date
+    FROM pmjay_packages
+    WHERE code = $1
+"#;
+
+/// PM-JAY packages among $1
+pub const GET_PMJAY_PACKAGES: &str = r#"
+    SELECT code, specialty, name, base_rate, preauth_required, day_care, hbp_version, effective_date,
+           termination_date
+    FROM pmjay_packages
+    WHERE code = ANY($1)
+"#;
+
+/// PM-JAY packages, optionally of one specialty ($1); by code
+pub const LIST_PMJAY_PACKAGES: &str = r#"
+    SELECT code, specialty, name, base_rate, preauth_required, day_care, hbp_version, effective_date,
+           termination_date
+    FROM pmjay_packages
+    WHERE ($1::text IS NULL OR specialty = $1)
+    ORDER BY code
+    LIMIT 1000
+"#;
+
+/// Add a fee schedule to a payer's contract
+pub const INSERT_FEE_SCHEDULE: &str = r#"
+    INSERT INTO fee_schedules (id, payer_id, payer_name, plan, name, effective_date, termination_date,
+                               percent_of_charges)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING id, payer_id, payer_name, plan, name, effective_date, termination_date, percent_of_charges,
+              created_at, updated_at
+"#;
+
+/// Get fee schedule by ID
+pub const GET_FEE_SCHEDULE_BY_ID: &str = r#"
+    SELECT id, payer_id, payer_name, plan, name, effective_date, termination_date, percent_of_charges,
+           created_at, updated_at
+    FROM fee_schedules
+    WHERE id = $1
+"#;
+
+/// Fee schedules, optionally of one payer ($1); by payer and effective date
+pub const LIST_FEE_SCHEDULES: &str = r#"
+    SELECT id, payer_id, payer_name, plan, name, effective_date, termination_date, percent_of_charges,
+           created_at, updated_at
+    FROM fee_schedules
+    WHERE ($1::text IS NULL OR payer_id = $1)
+    ORDER BY payer_id, plan NULLS FIRST, effective_date
+"#;
+
+/// Fee schedules of the payers among $1
+pub const GET_PAYER_FEE_SCHEDULES: &str = r#"
+    SELECT id, payer_id, payer_name, plan, name, effective_date, termination_date, percent_of_charges,
+           created_at, updated_at
+    FROM fee_schedules
+    WHERE payer_id = ANY($1)
+"#;
+
+/// Add a rate to a fee schedule, or replace it
+pub const UPSERT_SCHEDULE_RATE: &str = r#"
+    INSERT INTO fee_schedule_rates (fee_schedule_id, code_system, code, modifier, rate, effective_date,
+                                    termination_date)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    ON CONFLICT (fee_schedule_id, code_system, code, modifier, effective_date) DO UPDATE
+    SET rate = EXCLUDED.rate, termination_date = EXCLUDED.termination_date
+"#;
+
+/// A fee schedule's rates, by code
+pub const LIST_SCHEDULE_RATES: &str = r#"
+    SELECT fee_schedule_id, code_system, code, modifier, rate, effective_date, termination_date
+    FROM fee_schedule_rates
+    WHERE fee_schedule_id = $1
+    ORDER BY code, modifier, effective_date
+"#;
+
+/// Rates of the fee schedules among $1 for the codes among $2
+pub const GET_SCHEDULE_RATES_AMONG: &str = r#"
+    SELECT fee_schedule_id, code_system, code, modifier, rate, effective_date, termination_date
+    FROM fee_schedule_rates
+    WHERE fee_schedule_id = ANY($1) AND code = ANY($2)
+"#;
+
+/// Charges among $1
+pub const GET_CHARGES_AMONG: &str = r#"
+    SELECT id, patient_id, encounter_id, code_system, code, modifiers, ndc, units, unit_price, service_date,
+           status, captured_by, void_reason, created_at, updated_at
+    FROM charges
+    WHERE id = ANY($1)
+"#;
+
+/// Post a remittance line against a charge
+pub const INSERT_CHARGE_PAYMENT: &str = r#"
+    INSERT INTO charge_payments (id, charge_id, payer_id, plan, payer_claim_id, paid_date, paid,
+                                 patient_responsibility, adjustments, posted_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    RETURNING id, charge_id, payer_id, plan, payer_claim_id, paid_date, paid, patient_responsibility,
+              adjustments, posted_by, created_at
+"#;
+
+/// Remittance lines of the charges among $1, in the order they were posted
+pub const GET_CHARGE_PAYMENTS: &str = r#"
+    SELECT id, charge_id, payer_id, plan, payer_claim_id, paid_date, paid, patient_responsibility,
+           adjustments, posted_by, created_at
+    FROM charge_payments
+    WHERE charge_id = ANY($1)
+    ORDER BY created_at
+"#;
+
+/// Charges with remittance lines paid from $1 to $2, optionally by one
+/// payer ($3)
+pub const GET_PAID_CHARGE_IDS: &str = r#"
+    SELECT DISTINCT charge_id
+    FROM charge_payments
+    WHERE paid_date >= $1 AND paid_date <= $2
+      AND ($3::text IS NULL OR payer_id = $3)
+    LIMIT 5000
+"#;
@@ -0,0 +1,69 @@
+//! Billing Module
+//!
+//! This module provides charge capture for US billing and invoicing for
+//! Indian billing:
+//! - CPT, HCPCS Level II, NDC and modifier code sets, loaded from their
+//!   releases, with lookup
+//! - Charge capture, checking codes are billable on the date of service and
+//!   modifiers are well formed and go together
+//! - NCCI procedure-to-procedure edits, applied as charges are captured so
+//!   code pairs a payer would deny are caught before the claim is submitted
+//! - Payer contracts: fee schedules per payer and plan with effective-dated
+//!   rates, the contractual adjustment of a charge under them, and expected
+//!   against actual reimbursement as remittance lines are posted
+//! - GST invoices, numbered in a series per registration and financial
+//!   year, with each line's tax broken up into CGST and SGST, or IGST
+//! - The Ayushman Bharat PM-JAY package master, and pre-authorization
+//!   requests for packages as FHIR Bundles for the National Health Claims
+//!   Exchange
+
+#[path = "billing.controller.rs"]
+pub mod billing_controller;
+#[path = "billing.service.rs"]
+pub mod billing_service;
+#[path = "billing.sql.rs"]
+pub mod billing_sql;
+#[path = "billing.edits.rs"]
+pub mod billing_edits;
+#[path = "billing.contract.rs"]
+pub mod billing_contract;
+#[path = "billing.gst.rs"]
+pub mod billing_gst;
+#[path = "billing.pmjay.rs"]
+pub mod billing_pmjay;
+
+pub use billing_controller::BillingController;
+pub use billing_pmjay::PmjayConfig;
+pub use billing_service::{BillingCodeSystem, BillingService, Charge, GstInvoice};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::patient::PatientService;
+use crate::utils::api_router::ApiRouter;
+
+/// Billing Module Configuration
+pub struct BillingModule {
+    pub service: Arc<BillingService>,
+    pub controller: Arc<BillingController>,
+}
+
+impl BillingModule {
+    /// Create a new Billing Module with dependency injection
+    pub fn new(db_pool: PgPool, patients: Arc<PatientService>, pmjay: PmjayConfig) -> Self {
+        let service = Arc::new(BillingService::new(db_pool, patients, pmjay));
+        let controller = Arc::new(BillingController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<BillingService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,612 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::CARE_PLAN_RESOURCE_TYPE;
+use crate::models::{
+    AuditAction, AuditEventType, AuditLog, CarePlan, CarePlanActivity, CodeableConcept, Reference, ResourceMeta,
+};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::care_plan::care_plan_service::{CarePlanRequest, CarePlanSearch, CarePlanSearchResult};
+use crate::modules::care_plan::CarePlanService;
+use crate::modules::task::task_controller::{Annotation, Period, TaskResponse};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::task::TaskController;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::etag::{if_match_version, precondition_status, versioned, Versioned};
+use crate::utils::fhir_search::{EntrySearch, SearchEntryMode};
+use crate::utils::pagination::{page_links, BundleLink};
+
+/// FHIR R4 CarePlan as served
+#[derive(Debug, Serialize)]
+pub struct CarePlanResponse {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    pub status: String,
+    pub intent: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub subject: Reference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<Reference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<Reference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub activity: Vec<ActivityResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+/// FHIR CarePlan.activity, identified by the element id tasks refer to
+#[derive(Debug, Serialize)]
+pub struct ActivityResponse {
+    pub id: Uuid,
+    pub detail: ActivityDetail,
+}
+
+/// FHIR CarePlan.activity.detail
+#[derive(Debug, Serialize)]
+pub struct ActivityDetail {
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "scheduledTiming")]
+    pub scheduled_timing: Option<Timing>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// FHIR Timing, as an activity schedule
+#[derive(Debug, Serialize)]
+pub struct Timing {
+    pub repeat: TimingRepeat,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimingRepeat {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "boundsPeriod")]
+    pub bounds_period: Option<Period>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    pub frequency: u32,
+    pub period: u32,
+    #[serde(rename = "periodUnit")]
+    pub period_unit: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CarePlanBundle {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub link: Vec<BundleLink>,
+    pub entry: Vec<CarePlanBundleEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CarePlanBundleEntry {
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    pub resource: CarePlanResponse,
+    /// Set in searchsets only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<EntrySearch>,
+}
+
+/// How far ahead to schedule a plan's activities
+#[derive(Debug, Deserialize)]
+pub struct ScheduleRequest {
+    pub until: DateTime<Utc>,
+}
+
+/// Tasks created by scheduling a plan
+#[derive(Debug, Serialize)]
+pub struct ScheduleResponse {
+    pub scheduled: usize,
+    pub tasks: Vec<TaskResponse>,
+}
+
+/// A plan's care team, with the patient's standing team
+#[derive(Debug, Serialize)]
+pub struct CareTeamResponse {
+    pub care_plan_id: Uuid,
+    pub members: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Care plan controller for FHIR R4 CarePlans, their scheduling and care
+/// teams. A plan belongs to its patient's chart: reading it takes read
+/// access to the patient and changing or scheduling it update access.
+/// Changing its care team grants access to the patient, so that takes write
+/// access. Each is audited against the patient.
+pub struct CarePlanController {
+    state: CarePlanState,
+}
+
+#[derive(Clone)]
+pub struct CarePlanState {
+    service: Arc<CarePlanService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    audit_service: Arc<AuditService>,
+}
+
+impl CarePlanController {
+    /// Create new controller with injected services
+    pub fn new(
+        care_plan_service: Arc<CarePlanService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        audit_service: Arc<AuditService>,
+    ) -> Self {
+        Self {
+            state: CarePlanState {
+                service: care_plan_service,
+                authorization_engine,
+                audit_service,
+            },
+        }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_care_plan, "Record a care plan")
+            .get("/", Self::search_care_plans, "Search care plans with query parameters")
+            .get("/:id", Self::get_care_plan, "Get care plan by ID")
+            .put("/:id", Self::update_care_plan, "Update care plan")
+            .delete("/:id", Self::delete_care_plan, "Delete care plan")
+            .post("/:id/$schedule", Self::schedule_care_plan, "Create tasks for a care plan's scheduled activities")
+            .get("/:id/care-team", Self::get_care_team, "List a care plan's care team")
+            .put("/:id/care-team/:user_id", Self::add_care_team_member, "Add a user to a care plan's care team")
+            .delete("/:id/care-team/:user_id", Self::remove_care_team_member, "Remove a user from a care plan's care team")
+            .with_state(self.state.clone())
+    }
+
+    /// Record a care plan
+    pub async fn create_care_plan(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Json(request): Json<CarePlanRequest>,
+    ) -> Result<(StatusCode, Versioned<CarePlanResponse>), ErrorReply> {
+        tracing::info!("Recording care plan for patient {}", request.patient_id);
+
+        let context = "Failed to record care plan";
+        Self::authorize(&state, &auth, &headers, Action::Update, request.patient_id, context).await?;
+        match state.service.create(request).await {
+            Ok(care_plan) => {
+                tracing::info!("Care plan recorded successfully: {}", care_plan.id);
+                let event = (AuditEventType::Create, AuditAction::Create);
+                Self::audit(&state, &auth, event, &care_plan, "Care plan recorded".to_string(), context).await?;
+                Ok((StatusCode::CREATED, versioned(&care_plan.meta.clone(), Self::care_plan_to_response(care_plan))))
+            }
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Get care plan by ID
+    pub async fn get_care_plan(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Versioned<CarePlanResponse>, ErrorReply> {
+        let context = "Failed to get care plan";
+        let care_plan = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Read, care_plan.patient_id, context).await?;
+        let event = (AuditEventType::Access, AuditAction::Read);
+        Self::audit(&state, &auth, event, &care_plan, "Care plan viewed".to_string(), context).await?;
+        Ok(versioned(&care_plan.meta.clone(), Self::care_plan_to_response(care_plan)))
+    }
+
+    /// Search care plans with query parameters. Plans of patients the user
+    /// may not read are left out of the page.
+    pub async fn search_care_plans(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<Json<CarePlanBundle>, ErrorReply> {
+        tracing::info!("Searching care plans with params: {:?}", params);
+
+        let context = "Failed to search care plans";
+        let search = CarePlanSearch::from_params(&params)
+            .map_err(|e| Self::error_response("Invalid search parameters", e))?;
+        let mut result = state.service.search(&search).await.map_err(|e| Self::error_response(context, e))?;
+
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), &auth, &headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        let found = result.care_plans.len();
+        result.care_plans = authorizer
+            .retain(Action::Read, result.care_plans, |care_plan| Resource::Patient(care_plan.patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        if result.care_plans.len() < found {
+            let withheld = found - result.care_plans.len();
+            tracing::info!("Withheld {} of {} care plans from user {}", withheld, found, auth.user_id);
+        }
+        tracing::info!("Found {} care plans", result.care_plans.len());
+        for care_plan in &result.care_plans {
+            let event = (AuditEventType::Access, AuditAction::Read);
+            Self::audit(&state, &auth, event, care_plan, "Care plan search".to_string(), context).await?;
+        }
+
+        let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+        Ok(Json(Self::care_plans_to_bundle(result, link)))
+    }
+
+    /// Update care plan; `If-Match` must name the version being replaced
+    pub async fn update_care_plan(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<CarePlanRequest>,
+    ) -> Result<Versioned<CarePlanResponse>, ErrorReply> {
+        tracing::info!("Updating care plan: {}", id);
+
+        let expected_version = if_match_version(&headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the care plan's ETag in If-Match to update it".to_string(),
+                }),
+            )
+        })?;
+
+        let context = "Failed to update care plan";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.update(id, request, &expected_version).await {
+            Ok(Some(care_plan)) => {
+                let event = (AuditEventType::Update, AuditAction::Update);
+                Self::audit(&state, &auth, event, &care_plan, "Care plan updated".to_string(), context).await?;
+                Ok(versioned(&care_plan.meta.clone(), Self::care_plan_to_response(care_plan)))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Delete care plan
+    pub async fn delete_care_plan(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Deleting care plan: {}", id);
+
+        let context = "Failed to delete care plan";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.delete(id).await {
+            Ok(true) => {
+                let event = (AuditEventType::Delete, AuditAction::Delete);
+                Self::audit(&state, &auth, event, &current, "Care plan deleted".to_string(), context).await?;
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Create tasks for the plan's scheduled activities up to `until`
+    pub async fn schedule_care_plan(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ScheduleRequest>,
+    ) -> Result<Json<ScheduleResponse>, ErrorReply> {
+        tracing::info!("Scheduling care plan {} until {}", id, request.until);
+
+        let context = "Failed to schedule care plan";
+        let care_plan = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, care_plan.patient_id, context).await?;
+        match state.service.schedule(id, Utc::now(), request.until).await {
+            Ok(Some(tasks)) => {
+                tracing::info!("Scheduled {} tasks for care plan {}", tasks.len(), id);
+                let event = (AuditEventType::Create, AuditAction::Create);
+                let details = format!("Scheduled {} tasks until {}", tasks.len(), request.until);
+                Self::audit(&state, &auth, event, &care_plan, details, context).await?;
+                Ok(Json(ScheduleResponse {
+                    scheduled: tasks.len(),
+                    tasks: tasks.into_iter().map(TaskController::task_to_response).collect(),
+                }))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// The plan's care team, with the patient's standing team
+    pub async fn get_care_team(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<CareTeamResponse>, ErrorReply> {
+        let context = "Failed to get care team";
+        let care_plan = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Read, care_plan.patient_id, context).await?;
+        match state.service.care_team(id).await {
+            Ok(Some(members)) => {
+                let event = (AuditEventType::Access, AuditAction::Read);
+                Self::audit(&state, &auth, event, &care_plan, "Care team viewed".to_string(), context).await?;
+                Ok(Json(CareTeamResponse { care_plan_id: id, members }))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Add a user to the plan's care team
+    pub async fn add_care_team_member(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path((id, user_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Adding {} to the care team of care plan {}", user_id, id);
+
+        let context = "Failed to add care team member";
+        let care_plan = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Write, care_plan.patient_id, context).await?;
+        match state.service.add_care_team_member(id, user_id, auth.user_id).await {
+            Ok(true) => {
+                let event = (AuditEventType::Update, AuditAction::Update);
+                let details = format!("Care team member added: {}", user_id);
+                Self::audit(&state, &auth, event, &care_plan, details, context).await?;
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Remove a user from the plan's care team
+    pub async fn remove_care_team_member(
+        State(state): State<CarePlanState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path((id, user_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Removing {} from the care team of care plan {}", user_id, id);
+
+        let context = "Failed to remove care team member";
+        let care_plan = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Write, care_plan.patient_id, context).await?;
+        match state.service.remove_care_team_member(id, user_id).await {
+            Ok(true) => {
+                let event = (AuditEventType::Update, AuditAction::Update);
+                let details = format!("Care team member removed: {}", user_id);
+                Self::audit(&state, &auth, event, &care_plan, details, context).await?;
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// The care plan, or 404
+    async fn existing(state: &CarePlanState, id: Uuid, context: &str) -> Result<CarePlan, ErrorReply> {
+        match state.service.get(id).await {
+            Ok(Some(care_plan)) => Ok(care_plan),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Fail with 403 unless the user may take `action` on the patient
+    async fn authorize(
+        state: &CarePlanState,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        action: Action,
+        patient_id: Uuid,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), auth, headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        authorizer
+            .authorize(action, Resource::Patient(patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// Record the user's access to or change of a care plan
+    async fn audit(
+        state: &CarePlanState,
+        auth: &AuthContext,
+        (event_type, action): (AuditEventType, AuditAction),
+        care_plan: &CarePlan,
+        details: String,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let audit_log = auth
+            .audit(AuditLog::new(event_type, action, CARE_PLAN_RESOURCE_TYPE.to_string()))
+            .with_patient(care_plan.patient_id)
+            .with_resource(care_plan.id)
+            .with_details(details);
+        state
+            .audit_service
+            .create_audit_log(&audit_log)
+            .await
+            .map(|_| ())
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// Convert CarePlan model to FHIR response format
+    pub fn care_plan_to_response(care_plan: CarePlan) -> CarePlanResponse {
+        let period = (care_plan.period_start.is_some() || care_plan.period_end.is_some()).then_some(Period {
+            start: care_plan.period_start,
+            end: care_plan.period_end,
+        });
+
+        CarePlanResponse {
+            resource_type: CARE_PLAN_RESOURCE_TYPE.to_string(),
+            id: care_plan.id,
+            meta: care_plan.meta,
+            status: care_plan.status.as_str().to_string(),
+            intent: care_plan.intent.as_str().to_string(),
+            category: care_plan.category,
+            title: care_plan.title,
+            description: care_plan.description,
+            subject: Reference {
+                reference: format!("Patient/{}", care_plan.patient_id),
+                display: None,
+            },
+            period,
+            author: care_plan.author_id.map(|id| Reference {
+                reference: format!("Practitioner/{}", id),
+                display: None,
+            }),
+            addresses: care_plan
+                .addresses
+                .iter()
+                .map(|id| Reference {
+                    reference: format!("Condition/{}", id),
+                    display: None,
+                })
+                .collect(),
+            activity: care_plan.activities.into_iter().map(Self::activity_to_response).collect(),
+            note: care_plan.note.into_iter().map(|text| Annotation { text }).collect(),
+        }
+    }
+
+    fn activity_to_response(activity: CarePlanActivity) -> ActivityResponse {
+        let scheduled_timing = activity.schedule.map(|schedule| Timing {
+            repeat: TimingRepeat {
+                bounds_period: (schedule.start.is_some() || schedule.end.is_some()).then_some(Period {
+                    start: schedule.start,
+                    end: schedule.end,
+                }),
+                count: schedule.count,
+                frequency: schedule.frequency,
+                period: schedule.period,
+                period_unit: schedule.period_unit.as_str().to_string(),
+            },
+        });
+
+        ActivityResponse {
+            id: activity.id,
+            detail: ActivityDetail {
+                kind: "Task".to_string(),
+                code: activity.code,
+                status: activity.status.as_str().to_string(),
+                scheduled_timing,
+                performer: activity
+                    .performer_id
+                    .map(|id| Reference {
+                        reference: format!("Practitioner/{}", id),
+                        display: None,
+                    })
+                    .into_iter()
+                    .collect(),
+                description: activity.description,
+            },
+        }
+    }
+
+    /// Convert a page of search results to a FHIR searchset Bundle
+    fn care_plans_to_bundle(result: CarePlanSearchResult, link: Vec<BundleLink>) -> CarePlanBundle {
+        let search = EntrySearch {
+            mode: SearchEntryMode::Match,
+        };
+        let entry = result
+            .care_plans
+            .into_iter()
+            .map(|care_plan| CarePlanBundleEntry {
+                full_url: format!("CarePlan/{}", care_plan.id),
+                resource: Self::care_plan_to_response(care_plan),
+                search: Some(search.clone()),
+            })
+            .collect();
+
+        CarePlanBundle {
+            resource_type: "Bundle".to_string(),
+            id: Uuid::new_v4(),
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: Utc::now(),
+                profile: vec!["http://hl7.org/fhir/StructureDefinition/Bundle".to_string()],
+                security: vec![],
+                tag: vec![],
+            },
+            bundle_type: "searchset".to_string(),
+            total: result.total,
+            link,
+            entry,
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn not_found(id: Uuid) -> ErrorReply {
+        tracing::warn!("Care plan not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Care plan not found".to_string(),
+                message: format!("Care plan with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => precondition_status(&e).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
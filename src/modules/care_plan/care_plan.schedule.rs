@@ -0,0 +1,133 @@
+//! When care plan activities fall due
+//!
+//! An activity repeats `frequency` times every `period` `period_unit`s from
+//! its start, spread evenly over each period. Scheduling turns the
+//! occurrences in a window into tasks.
+
+use chrono::{DateTime, Duration, Months, Utc};
+
+use crate::core::HimsError;
+use crate::models::{ActivitySchedule, ScheduleUnit};
+
+/// Most occurrences of one activity scheduled at a time
+pub const MAX_OCCURRENCES: usize = 366;
+
+/// Start of the period `periods` units after `start`, or `None` past the
+/// end of time
+fn period_start(start: DateTime<Utc>, unit: ScheduleUnit, periods: u32) -> Option<DateTime<Utc>> {
+    match unit {
+        ScheduleUnit::Hour => start.checked_add_signed(Duration::hours(periods.into())),
+        ScheduleUnit::Day => start.checked_add_signed(Duration::days(periods.into())),
+        ScheduleUnit::Week => start.checked_add_signed(Duration::weeks(periods.into())),
+        // Calendar months, so a plan started on the 31st recurs on the last
+        // day of shorter months
+        ScheduleUnit::Month => start.checked_add_months(Months::new(periods)),
+    }
+}
+
+/// Check a schedule's repetition is well formed
+pub fn check_schedule(schedule: &ActivitySchedule) -> Result<(), HimsError> {
+    let invalid = |message: &str| HimsError::ValidationError { message: message.to_string() };
+
+    if schedule.frequency == 0 || schedule.period == 0 {
+        return Err(invalid("Activity schedule frequency and period must be at least 1"));
+    }
+    if schedule.count == Some(0) {
+        return Err(invalid("Activity schedule count must be at least 1"));
+    }
+    if let (Some(start), Some(end)) = (schedule.start, schedule.end) {
+        if end < start {
+            return Err(invalid("Activity schedule ends before it starts"));
+        }
+    }
+    Ok(())
+}
+
+/// When an activity falls due from `from` up to and including `until`, in
+/// order. `start` and `end` stand in for a schedule without its own,
+/// normally the care plan's period. `count` limits the activity as a
+/// whole, so occurrences before `from` count towards it.
+pub fn occurrences(
+    schedule: &ActivitySchedule,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, HimsError> {
+    check_schedule(schedule)?;
+    let start = schedule.start.or(start).ok_or_else(|| HimsError::ValidationError {
+        message: "Activity schedule needs a start, or the care plan a period start".to_string(),
+    })?;
+    let end = schedule.end.or(end).map_or(until, |end| end.min(until));
+
+    let mut due = Vec::new();
+    let mut seen = 0;
+    for k in 0u32.. {
+        let this_period = k.checked_mul(schedule.period).and_then(|n| period_start(start, schedule.period_unit, n));
+        let next_period = (k + 1).checked_mul(schedule.period).and_then(|n| period_start(start, schedule.period_unit, n));
+        let (Some(this_period), Some(next_period)) = (this_period, next_period) else {
+            break;
+        };
+        let step = (next_period - this_period) / schedule.frequency as i32;
+        for i in 0..schedule.frequency {
+            let at = this_period + step * i as i32;
+            if at > end || schedule.count.is_some_and(|count| seen >= count) {
+                return Ok(due);
+            }
+            seen += 1;
+            if at >= from {
+                if due.len() == MAX_OCCURRENCES {
+                    return Err(HimsError::ValidationError {
+                        message: format!(
+                            "Activity falls due more than {} times before {}; schedule a shorter window",
+                            MAX_OCCURRENCES, until
+                        ),
+                    });
+                }
+                due.push(at);
+            }
+        }
+    }
+    Ok(due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn schedule(frequency: u32, period: u32, period_unit: ScheduleUnit) -> ActivitySchedule {
+        ActivitySchedule { frequency, period, period_unit, start: None, end: None, count: None }
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 31, 9, 0, 0).unwrap();
+        let until = start + Duration::days(7);
+
+        // Twice a day for a week, from the plan's start
+        let twice_daily = schedule(2, 1, ScheduleUnit::Day);
+        let due = occurrences(&twice_daily, Some(start), None, start, until).unwrap();
+        assert_eq!(due.len(), 15);
+        assert_eq!(due[1], start + Duration::hours(12));
+
+        // Occurrences before the window still count towards count
+        let limited = ActivitySchedule { count: Some(4), ..twice_daily.clone() };
+        let due = occurrences(&limited, Some(start), None, start + Duration::days(1), until).unwrap();
+        assert_eq!(due, vec![start + Duration::days(1), start + Duration::hours(36)]);
+
+        // The plan's end cuts the window short
+        let due = occurrences(&twice_daily, Some(start), Some(start + Duration::days(1)), start, until).unwrap();
+        assert_eq!(due.len(), 3);
+
+        // Monthly from the 31st falls on the last day of February
+        let monthly = schedule(1, 1, ScheduleUnit::Month);
+        let due = occurrences(&monthly, Some(start), None, start, start + Duration::days(60)).unwrap();
+        assert_eq!(due[1], Utc.with_ymd_and_hms(2024, 2, 29, 9, 0, 0).unwrap());
+
+        assert!(occurrences(&twice_daily, None, None, start, until).is_err(), "no start");
+        assert!(occurrences(&schedule(0, 1, ScheduleUnit::Day), Some(start), None, start, until).is_err());
+        let hourly = schedule(1, 1, ScheduleUnit::Hour);
+        assert!(occurrences(&hourly, Some(start), None, start, start + Duration::days(30)).is_err(), "too many");
+    }
+}
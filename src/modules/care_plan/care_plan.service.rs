@@ -0,0 +1,650 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{CarePlan, CarePlanActivity, CarePlanIntent, CarePlanStatus, CodeableConcept, Task};
+use crate::modules::care_plan::care_plan_schedule::{check_schedule, occurrences};
+use crate::modules::care_plan::CareTeam;
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::modules::task::TaskService;
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{reference_id, split_modifier, SearchParamDefinition, TotalMode};
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
+
+// Import SQL queries from separate file
+use crate::modules::care_plan::care_plan_sql::*;
+
+/// Care plan from a row of any query selecting its columns
+fn care_plan_from_row(row: &PgRow) -> CarePlan {
+    CarePlan {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        status: CarePlanStatus::from_string(row.get("status")),
+        intent: CarePlanIntent::from_string(row.get("intent")),
+        title: row.get("title"),
+        description: row.get("description"),
+        category: serde_json::from_value(row.get("category")).unwrap_or_default(),
+        period_start: row.get("period_start"),
+        period_end: row.get("period_end"),
+        author_id: row.get("author_id"),
+        addresses: row.get("addresses"),
+        activities: serde_json::from_value(row.get("activities")).unwrap_or_default(),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
+    }
+}
+
+/// Check a care plan before it is saved: its period must not end before it
+/// starts, activity IDs must be unique, and activity schedules must be well
+/// formed
+pub fn check_care_plan(care_plan: &CarePlan) -> Result<(), HimsError> {
+    let invalid = |message: String| HimsError::ValidationError { message };
+
+    if let (Some(start), Some(end)) = (care_plan.period_start, care_plan.period_end) {
+        if end < start {
+            return Err(invalid("Care plan period ends before it starts".to_string()));
+        }
+    }
+    for (i, activity) in care_plan.activities.iter().enumerate() {
+        if care_plan.activities[..i].iter().any(|earlier| earlier.id == activity.id) {
+            return Err(invalid(format!("Care plan has two activities with id {}", activity.id)));
+        }
+        if activity.code.is_none() && activity.description.as_deref().unwrap_or("").trim().is_empty() {
+            return Err(invalid(format!("Activity {} needs a code or a description", activity.id)));
+        }
+        if let Some(schedule) = &activity.schedule {
+            check_schedule(schedule)?;
+        }
+    }
+    Ok(())
+}
+
+/// A care plan as clients send it to record or replace one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarePlanRequest {
+    pub patient_id: Uuid,
+    #[serde(default)]
+    pub status: CarePlanStatus,
+    #[serde(default)]
+    pub intent: CarePlanIntent,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub category: Vec<CodeableConcept>,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
+    pub author_id: Option<Uuid>,
+    /// Conditions the plan addresses
+    #[serde(default)]
+    pub addresses: Vec<Uuid>,
+    #[serde(default)]
+    pub activities: Vec<CarePlanActivity>,
+    pub note: Option<String>,
+}
+
+impl From<CarePlanRequest> for CarePlan {
+    fn from(request: CarePlanRequest) -> Self {
+        let mut care_plan = CarePlan::new(request.patient_id, request.intent);
+        care_plan.status = request.status;
+        care_plan.title = request.title;
+        care_plan.description = request.description;
+        care_plan.category = request.category;
+        care_plan.period_start = request.period_start;
+        care_plan.period_end = request.period_end;
+        care_plan.author_id = request.author_id;
+        care_plan.addresses = request.addresses;
+        care_plan.activities = request.activities;
+        care_plan.note = request.note;
+        care_plan
+    }
+}
+
+/// Page size of a care plan search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 50;
+
+/// Largest page a care plan search returns
+pub const MAX_SEARCH_COUNT: i64 = 200;
+
+/// Paging of care plan searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Order of care plan searches, most recently created first, as
+/// SEARCH_CARE_PLANS keys its rows
+const SEARCH_KEYS: [SortKey; 1] = [SortKey::new("created_at", "timestamptz", true)];
+
+/// Parsed care plan search
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarePlanSearch {
+    pub patient: Vec<Uuid>,
+    /// Status codes; any matches
+    pub status: Vec<String>,
+    /// Intent codes; any matches
+    pub intent: Vec<String>,
+    /// Conditions addressed; plans addressing any match
+    pub condition: Vec<Uuid>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for CarePlanSearch {
+    fn default() -> Self {
+        Self {
+            patient: Vec::new(),
+            status: Vec::new(),
+            intent: Vec::new(),
+            condition: Vec::new(),
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// A page of care plan search results
+#[derive(Debug)]
+pub struct CarePlanSearchResult {
+    pub care_plans: Vec<CarePlan>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl CarePlanSearch {
+    /// Search parameters `from_params` supports, for the CapabilityStatement
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "patient",
+            param_type: "reference",
+            documentation: "The patient the care plan is for",
+        },
+        SearchParamDefinition {
+            name: "status",
+            param_type: "token",
+            documentation: "Care plan status, e.g. active; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "intent",
+            param_type: "token",
+            documentation: "proposal, plan, order or option; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "condition",
+            param_type: "reference",
+            documentation: "A condition the care plan addresses",
+        },
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored; invalid
+    /// values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+
+        for (param, value) in params {
+            let (name, modifier) = split_modifier(param);
+            match (name, modifier) {
+                ("patient", None | Some("Patient")) => {
+                    for reference in value.split(',') {
+                        search.patient.push(reference_id(reference, "Patient")?);
+                    }
+                }
+                ("status", None) => {
+                    for code in value.split(',') {
+                        if CarePlanStatus::from_string(code).as_str() != code {
+                            return Err(invalid(name, code));
+                        }
+                        search.status.push(code.to_string());
+                    }
+                }
+                ("intent", None) => {
+                    for code in value.split(',') {
+                        if CarePlanIntent::from_string(code).as_str() != code {
+                            return Err(invalid(name, code));
+                        }
+                        search.intent.push(code.to_string());
+                    }
+                }
+                ("condition", None | Some("Condition")) => {
+                    for reference in value.split(',') {
+                        search.condition.push(reference_id(reference, "Condition")?);
+                    }
+                }
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported care plan search parameter {}", param),
+            }
+        }
+        search.page.check_cursor(&SEARCH_KEYS)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on care plans for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if !self.patient.is_empty() {
+            query.push(" AND patient_id = ANY(").push_bind(self.patient.clone()).push(")");
+        }
+        if !self.status.is_empty() {
+            query.push(" AND status = ANY(").push_bind(self.status.clone()).push(")");
+        }
+        if !self.intent.is_empty() {
+            query.push(" AND intent = ANY(").push_bind(self.intent.clone()).push(")");
+        }
+        if !self.condition.is_empty() {
+            query.push(" AND addresses && ").push_bind(self.condition.clone());
+        }
+    }
+}
+
+/// Care plan service for chronic-care programs: plans, their scheduled
+/// activities, the tasks scheduled from them, and plan care teams
+pub struct CarePlanService {
+    pool: PgPool,
+    events: EventBus,
+    tasks: Arc<TaskService>,
+    care_team: Arc<CareTeam>,
+}
+
+impl CarePlanService {
+    /// Create the service scheduling tasks with `tasks` and publishing to a
+    /// shared event bus
+    pub fn with_events(pool: PgPool, events: EventBus, tasks: Arc<TaskService>) -> Self {
+        let care_team = tasks.care_team();
+        Self {
+            pool,
+            events,
+            tasks,
+            care_team,
+        }
+    }
+
+    fn bind_json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap_or_default()
+    }
+
+    /// Record a care plan
+    pub async fn create(&self, request: CarePlanRequest) -> Result<CarePlan, HimsError> {
+        let care_plan: CarePlan = request.into();
+        check_care_plan(&care_plan)?;
+
+        sqlx::query(INSERT_CARE_PLAN)
+            .bind(care_plan.id)
+            .bind(care_plan.patient_id)
+            .bind(care_plan.status.as_str())
+            .bind(care_plan.intent.as_str())
+            .bind(&care_plan.title)
+            .bind(&care_plan.description)
+            .bind(Self::bind_json(&care_plan.category))
+            .bind(care_plan.period_start)
+            .bind(care_plan.period_end)
+            .bind(care_plan.author_id)
+            .bind(&care_plan.addresses)
+            .bind(Self::bind_json(&care_plan.activities))
+            .bind(&care_plan.note)
+            .bind(care_plan.created_at)
+            .bind(care_plan.updated_at)
+            .bind(Self::bind_json(&care_plan.meta))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.events.publish(DomainEvent::PlanCreated {
+            care_plan_id: care_plan.id,
+            patient_id: care_plan.patient_id,
+        });
+        Ok(care_plan)
+    }
+
+    /// Get care plan by ID
+    pub async fn get(&self, id: Uuid) -> Result<Option<CarePlan>, HimsError> {
+        let row = sqlx::query(GET_CARE_PLAN_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(row.as_ref().map(care_plan_from_row))
+    }
+
+    /// Search care plans, a page at a time
+    pub async fn search(&self, search: &CarePlanSearch) -> Result<CarePlanSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_CARE_PLANS);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &SEARCH_KEYS);
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut fetched = Vec::new();
+        for row in rows {
+            let care_plan = care_plan_from_row(&row);
+            fetched.push((care_plan, RowKey::from_column(row.get("page_key"))?));
+        }
+        let page = search.page.page(fetched);
+
+        let total = count_matches(
+            &self.pool,
+            search.page.total(),
+            COUNT_CARE_PLANS,
+            ESTIMATE_CARE_PLANS,
+            |query| search.push_filters(query),
+        )
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(CarePlanSearchResult {
+            care_plans: page.items,
+            total,
+            next: page.next,
+            previous: page.previous,
+        })
+    }
+
+    /// Replace a care plan if it is still at `expected_version`, or `None`
+    /// if there is no such plan. The status may only move forward: drafts
+    /// become active, active plans can be put on hold, and ended plans
+    /// stay ended. Open tasks are cancelled for a plan that ends and for
+    /// activities that are no longer carried out.
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: CarePlanRequest,
+        expected_version: &str,
+    ) -> Result<Option<CarePlan>, HimsError> {
+        let Some(current) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let current_version = current.meta.version_id.as_deref().unwrap_or("1");
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Care plan {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+        if request.patient_id != current.patient_id {
+            return Err(HimsError::ValidationError {
+                message: format!("Care plan {} is for patient {}", id, current.patient_id),
+            });
+        }
+        if !current.status.can_transition_to(request.status) {
+            return Err(HimsError::ValidationError {
+                message: format!(
+                    "Care plan {} cannot go from {} to {}",
+                    id,
+                    current.status.as_str(),
+                    request.status.as_str()
+                ),
+            });
+        }
+
+        let care_plan: CarePlan = request.into();
+        check_care_plan(&care_plan)?;
+        let rows_affected = sqlx::query(UPDATE_CARE_PLAN)
+            .bind(id)
+            .bind(care_plan.status.as_str())
+            .bind(care_plan.intent.as_str())
+            .bind(&care_plan.title)
+            .bind(&care_plan.description)
+            .bind(Self::bind_json(&care_plan.category))
+            .bind(care_plan.period_start)
+            .bind(care_plan.period_end)
+            .bind(care_plan.author_id)
+            .bind(&care_plan.addresses)
+            .bind(Self::bind_json(&care_plan.activities))
+            .bind(&care_plan.note)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Care plan {} was modified concurrently", id),
+            });
+        }
+        self.events.publish(DomainEvent::PlanUpdated { care_plan_id: id });
+
+        if care_plan.status.is_terminal() {
+            let reason = format!("Care plan {}", care_plan.status.as_str());
+            self.tasks.cancel_open_for_plan(id, None, &reason).await?;
+        } else {
+            let stopped = Self::stopped_activities(&current, &care_plan);
+            if !stopped.is_empty() {
+                self.tasks
+                    .cancel_open_for_plan(id, Some(stopped), "Care plan activity stopped")
+                    .await?;
+            }
+        }
+
+        self.get(id).await
+    }
+
+    /// Activities of `current` that `updated` removes or no longer carries out
+    fn stopped_activities(current: &CarePlan, updated: &CarePlan) -> Vec<Uuid> {
+        current
+            .activities
+            .iter()
+            .filter(|activity| {
+                !updated
+                    .activity(activity.id)
+                    .is_some_and(|updated| updated.status.is_schedulable())
+            })
+            .map(|activity| activity.id)
+            .collect()
+    }
+
+    /// Soft delete care plan, cancelling its open tasks
+    pub async fn delete(&self, id: Uuid) -> Result<bool, HimsError> {
+        let rows_affected = sqlx::query(SOFT_DELETE_CARE_PLAN)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected > 0 {
+            self.events.publish(DomainEvent::PlanDeleted { care_plan_id: id });
+            self.tasks.cancel_open_for_plan(id, None, "Care plan deleted").await?;
+        }
+        Ok(rows_affected > 0)
+    }
+
+    /// Create tasks for the occurrences of an active plan's scheduled
+    /// activities from `now` until `until`, or `None` if there is no such
+    /// plan. Each task goes to the activity's performer, who must be on the
+    /// care team. Occurrences that already have a task are skipped, so a
+    /// plan can be scheduled again as its window moves on; the tasks
+    /// created are returned.
+    pub async fn schedule(
+        &self,
+        id: Uuid,
+        now: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Option<Vec<Task>>, HimsError> {
+        let Some(care_plan) = self.get(id).await? else {
+            return Ok(None);
+        };
+        if care_plan.status != CarePlanStatus::Active {
+            return Err(HimsError::ValidationError {
+                message: format!("Care plan {} is {}; only active plans are scheduled", id, care_plan.status.as_str()),
+            });
+        }
+        if until <= now {
+            return Err(HimsError::ValidationError {
+                message: "Schedule until a time in the future".to_string(),
+            });
+        }
+
+        let mut tasks = Vec::new();
+        for activity in &care_plan.activities {
+            let Some(schedule) = activity.schedule.as_ref().filter(|_| activity.status.is_schedulable()) else {
+                continue;
+            };
+            if let Some(performer_id) = activity.performer_id {
+                if !self.care_team.is_member(performer_id, care_plan.patient_id, Some(id)).await? {
+                    return Err(HimsError::ValidationError {
+                        message: format!(
+                            "Performer {} of activity {} is not on the care team",
+                            performer_id, activity.id
+                        ),
+                    });
+                }
+            }
+
+            for due in occurrences(schedule, care_plan.period_start, care_plan.period_end, now, until)? {
+                let mut task = Task::new(care_plan.patient_id);
+                task.care_plan_id = Some(id);
+                task.activity_id = Some(activity.id);
+                task.code = activity.code.clone();
+                task.description = activity.description.clone();
+                task.owner_id = activity.performer_id;
+                task.requester_id = care_plan.author_id;
+                task.due = Some(due);
+                tasks.push(task);
+            }
+        }
+
+        self.tasks.create_scheduled(tasks).await.map(Some)
+    }
+
+    /// Members of the plan's care team, with the patient's standing team,
+    /// or `None` if there is no such plan
+    pub async fn care_team(&self, id: Uuid) -> Result<Option<Vec<Uuid>>, HimsError> {
+        let Some(care_plan) = self.get(id).await? else {
+            return Ok(None);
+        };
+        self.care_team.members(care_plan.patient_id, Some(id)).await.map(Some)
+    }
+
+    /// Add a user to the plan's care team; `false` if there is no such plan
+    pub async fn add_care_team_member(&self, id: Uuid, user_id: Uuid, added_by: Uuid) -> Result<bool, HimsError> {
+        if self.get(id).await?.is_none() {
+            return Ok(false);
+        }
+        self.care_team.add(id, user_id, added_by).await?;
+        Ok(true)
+    }
+
+    /// Remove a user from the plan's care team; `false` if there is no such
+    /// plan
+    pub async fn remove_care_team_member(&self, id: Uuid, user_id: Uuid) -> Result<bool, HimsError> {
+        if self.get(id).await?.is_none() {
+            return Ok(false);
+        }
+        self.care_team.remove(id, user_id).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ActivitySchedule, CarePlanActivityStatus, ScheduleUnit};
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn glucose_checks() -> CarePlanActivity {
+        CarePlanActivity {
+            id: Uuid::new_v4(),
+            code: None,
+            description: Some("Check fasting blood glucose".to_string()),
+            status: CarePlanActivityStatus::NotStarted,
+            performer_id: None,
+            schedule: Some(ActivitySchedule {
+                frequency: 1,
+                period: 1,
+                period_unit: ScheduleUnit::Day,
+                start: None,
+                end: None,
+                count: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_check_care_plan() {
+        let mut care_plan = CarePlan::new(Uuid::new_v4(), CarePlanIntent::Plan);
+        care_plan.activities.push(glucose_checks());
+        assert!(check_care_plan(&care_plan).is_ok());
+
+        care_plan.activities.push(care_plan.activities[0].clone());
+        assert!(check_care_plan(&care_plan).is_err(), "duplicate activity id");
+        care_plan.activities.pop();
+
+        care_plan.period_start = Some(Utc::now());
+        care_plan.period_end = Some(Utc::now() - chrono::Duration::days(1));
+        assert!(check_care_plan(&care_plan).is_err(), "period ends before it starts");
+        care_plan.period_end = None;
+
+        care_plan.activities[0].schedule.as_mut().unwrap().frequency = 0;
+        assert!(check_care_plan(&care_plan).is_err(), "never repeats");
+    }
+
+    #[test]
+    fn test_status_workflow() {
+        assert!(CarePlanStatus::Draft.can_transition_to(CarePlanStatus::Active));
+        assert!(CarePlanStatus::Active.can_transition_to(CarePlanStatus::OnHold));
+        assert!(CarePlanStatus::OnHold.can_transition_to(CarePlanStatus::Completed));
+        assert!(!CarePlanStatus::Completed.can_transition_to(CarePlanStatus::Active));
+        assert!(!CarePlanStatus::Draft.can_transition_to(CarePlanStatus::Completed));
+        assert!(CarePlanStatus::Revoked.can_transition_to(CarePlanStatus::EnteredInError));
+
+        let mut current = CarePlan::new(Uuid::new_v4(), CarePlanIntent::Plan);
+        current.activities = vec![glucose_checks(), glucose_checks()];
+        let mut updated = current.clone();
+        updated.activities[0].status = CarePlanActivityStatus::Stopped;
+        updated.activities.pop();
+        assert_eq!(
+            CarePlanService::stopped_activities(&current, &updated),
+            vec![current.activities[0].id, current.activities[1].id]
+        );
+    }
+
+    #[test]
+    fn test_search_from_params() {
+        let patient = Uuid::new_v4();
+        let condition = Uuid::new_v4();
+        let search = CarePlanSearch::from_params(&params(&[
+            ("patient", format!("Patient/{}", patient).as_str()),
+            ("status", "active,on-hold"),
+            ("condition", format!("Condition/{}", condition).as_str()),
+        ]))
+        .unwrap();
+        assert_eq!(search.patient, vec![patient]);
+        assert_eq!(search.status, vec!["active".to_string(), "on-hold".to_string()]);
+
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM care_plans WHERE deleted_at IS NULL");
+        search.push_filters(&mut query);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM care_plans WHERE deleted_at IS NULL AND patient_id = ANY($1) AND status = ANY($2) AND addresses && $3"
+        );
+
+        assert!(CarePlanSearch::from_params(&params(&[("status", "finished")])).is_err());
+        assert!(CarePlanSearch::from_params(&params(&[("condition", "Patient/1")])).is_err());
+    }
+
+    #[test]
+    fn test_advertised_params_are_supported() {
+        let id = Uuid::new_v4();
+        for param in CarePlanSearch::SEARCH_PARAMS {
+            let value = match param.name {
+                "status" => "active".to_string(),
+                "intent" => "order".to_string(),
+                _ => id.to_string(),
+            };
+            let search = CarePlanSearch::from_params(&params(&[(param.name, value.as_str())])).unwrap();
+            assert_ne!(search, CarePlanSearch::default(), "{} was ignored", param.name);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Care Plan SQL Queries
+//! 
+//! This file contains all SQL queries used by the care plan service.
+
+/// Insert a new care plan
+pub const INSERT_CARE_PLAN: &str = r#"
+    INSERT INTO care_plans (
+        id, patient_id, status, intent, title, description, category, period_start, period_end,
+        author_id, addresses, activities, note, created_at, updated_at, meta
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16
+    )
+"#;
+
+/// Get care plan by ID
+pub const GET_CARE_PLAN_BY_ID: &str = r#"
+    SELECT id, patient_id, status, intent, title, description, category, period_start, period_end,
+           author_id, addresses, activities, note, created_at, updated_at, meta
+    FROM care_plans
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Replace a care plan if it is still at version $14, moving it to version
+/// $13. The patient a care plan is for never changes.
+pub const UPDATE_CARE_PLAN: &str = r#"
+    UPDATE care_plans
+    SET status = $2, intent = $3, title = $4, description = $5, category = $6,
+        period_start = $7, period_end = $8, author_id = $9, addresses = $10, activities = $11,
+        note = $12, updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($13::text))
+    WHERE id = $1 AND deleted_at IS NULL AND COALESCE(meta->>'version_id', '1') = $14
+"#;
+
+/// Soft delete care plan
+pub const SOFT_DELETE_CARE_PLAN: &str = r#"
+    UPDATE care_plans
+    SET deleted_at = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Start of a care plan search, most recently created first; the service
+/// appends the filters and the page's cursor, order and limit
+pub const SEARCH_CARE_PLANS: &str = r#"
+    SELECT id, patient_id, status, intent, title, description, category, period_start, period_end,
+           author_id, addresses, activities, note, created_at, updated_at, meta,
+           ARRAY[created_at::text, id::text] AS page_key
+    FROM care_plans
+    WHERE deleted_at IS NULL
+"#;
+
+/// Start of an accurate care plan search total
+pub const COUNT_CARE_PLANS: &str = r#"
+    SELECT COUNT(*) FROM care_plans WHERE deleted_at IS NULL
+"#;
+
+/// Query plan of a care plan search, whose row estimate answers
+/// `_total=estimate`
+pub const ESTIMATE_CARE_PLANS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM care_plans WHERE deleted_at IS NULL
+"#;
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::{
+    AuthorizationStorage, HealthcareRelation, PostgresAuthorizationStorage, RelationStorage, RelationshipTuple,
+    Resource, Subject,
+};
+
+/// Care teams, kept as CareTeamMember relations: on the patient for the
+/// patient's standing team, and on a care plan for members who work on that
+/// plan only
+pub struct CareTeam {
+    storage: Arc<PostgresAuthorizationStorage>,
+}
+
+impl CareTeam {
+    pub fn new(storage: Arc<PostgresAuthorizationStorage>) -> Self {
+        Self { storage }
+    }
+
+    /// Users on the patient's care team or the plan's, without duplicates
+    pub async fn members(&self, patient_id: Uuid, care_plan_id: Option<Uuid>) -> Result<Vec<Uuid>, HimsError> {
+        let mut resources = vec![Resource::Patient(patient_id)];
+        resources.extend(care_plan_id.map(Resource::CarePlan));
+
+        let mut members = Vec::new();
+        for resource in &resources {
+            let subjects = self
+                .storage
+                .find_direct_relationships(resource, &HealthcareRelation::CareTeamMember)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            for subject in subjects {
+                if let Subject::User(user_id) = subject {
+                    if !members.contains(&user_id) {
+                        members.push(user_id);
+                    }
+                }
+            }
+        }
+        Ok(members)
+    }
+
+    /// Whether the user is on the patient's care team or the plan's
+    pub async fn is_member(&self, user_id: Uuid, patient_id: Uuid, care_plan_id: Option<Uuid>) -> Result<bool, HimsError> {
+        let mut resources = vec![Resource::Patient(patient_id)];
+        resources.extend(care_plan_id.map(Resource::CarePlan));
+
+        for resource in &resources {
+            let is_member = self
+                .storage
+                .has_relationship(resource, &HealthcareRelation::CareTeamMember, &Subject::User(user_id))
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            if is_member {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Add the user to the plan's care team; adding a member again changes
+    /// nothing
+    pub async fn add(&self, care_plan_id: Uuid, user_id: Uuid, added_by: Uuid) -> Result<(), HimsError> {
+        let resource = Resource::CarePlan(care_plan_id);
+        let subject = Subject::User(user_id);
+        let is_member = self
+            .storage
+            .has_relationship(&resource, &HealthcareRelation::CareTeamMember, &subject)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        if is_member {
+            return Ok(());
+        }
+
+        let tuple = RelationshipTuple::new(resource, HealthcareRelation::CareTeamMember, subject).with_creator(added_by);
+        self.storage
+            .store_relationship(&tuple)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))
+    }
+
+    /// Remove the user from the plan's care team. Membership of the
+    /// patient's standing team is managed with the patient.
+    pub async fn remove(&self, care_plan_id: Uuid, user_id: Uuid) -> Result<(), HimsError> {
+        let tuple = RelationshipTuple::new(
+            Resource::CarePlan(care_plan_id),
+            HealthcareRelation::CareTeamMember,
+            Subject::User(user_id),
+        );
+        self.storage
+            .remove_relationship(&tuple)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))
+    }
+}
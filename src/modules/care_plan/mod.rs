@@ -0,0 +1,67 @@
+//! Care Plan Module
+//!
+//! This module provides chronic-care coordination with FHIR R4 CarePlans:
+//! - Plans for a patient, addressing their conditions
+//! - Activities with repeat schedules, turned into Tasks ahead of time
+//! - Care teams kept as CareTeamMember relations, for the patient and per
+//!   plan; tasks are only assigned within them
+//! - Status workflows that cancel the open tasks of ended plans and
+//!   stopped activities
+//! - Versioned updates guarded by `If-Match`
+//! - Access checked against the patient, and every access audited
+
+#[path = "care_plan.controller.rs"]
+pub mod care_plan_controller;
+#[path = "care_plan.schedule.rs"]
+pub mod care_plan_schedule;
+#[path = "care_plan.service.rs"]
+pub mod care_plan_service;
+#[path = "care_plan.sql.rs"]
+pub mod care_plan_sql;
+#[path = "care_plan.team.rs"]
+pub mod care_plan_team;
+
+pub use care_plan_controller::CarePlanController;
+pub use care_plan_service::{CarePlanRequest, CarePlanSearch, CarePlanSearchResult, CarePlanService};
+pub use care_plan_team::CareTeam;
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::audit::AuditService;
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::events::EventBus;
+use crate::modules::task::TaskService;
+use crate::utils::api_router::ApiRouter;
+
+/// Care Plan Module Configuration
+pub struct CarePlanModule {
+    pub service: Arc<CarePlanService>,
+    pub controller: Arc<CarePlanController>,
+}
+
+impl CarePlanModule {
+    /// Create a new Care Plan Module scheduling tasks with `tasks`
+    pub fn new(
+        db_pool: PgPool,
+        events: EventBus,
+        tasks: Arc<TaskService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        let audit_service = Arc::new(AuditService::new(db_pool.clone()));
+        let service = Arc::new(CarePlanService::with_events(db_pool, events, tasks));
+        let controller = Arc::new(CarePlanController::new(service.clone(), authorization_engine, audit_service));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<CarePlanService> {
+        self.service.clone()
+    }
+}
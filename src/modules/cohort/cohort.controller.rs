@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::cohort::cohort_service::{
+    CohortDefinition, CohortEvaluation, CohortMember, CohortMembersQuery, CohortPreview, CohortPreviewRequest,
+    CohortRequest,
+};
+use crate::modules::cohort::CohortService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Cohort controller for population health cohorts and their member lists
+pub struct CohortController {
+    cohort_service: Arc<CohortService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl CohortController {
+    /// Create new controller with injected service
+    pub fn new(cohort_service: Arc<CohortService>) -> Self {
+        Self { cohort_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create, "Save a cohort definition")
+            .get("/", Self::list, "List saved cohorts")
+            .post("/preview", Self::preview, "Count the patients meeting cohort criteria")
+            .get("/:id", Self::get, "Get a saved cohort")
+            .put("/:id", Self::update, "Replace a cohort's definition")
+            .delete("/:id", Self::delete, "Retire a cohort")
+            .post("/:id/evaluate", Self::evaluate, "Evaluate a cohort into its member list")
+            .get("/:id/members", Self::members, "List a cohort's members")
+            .with_state(self.cohort_service.clone())
+    }
+
+    /// Save a cohort definition
+    pub async fn create(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+        Json(payload): Json<CohortRequest>,
+    ) -> Result<(StatusCode, Json<CohortDefinition>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.create(payload, actor).await {
+            Ok(cohort) => Ok((StatusCode::CREATED, Json(cohort))),
+            Err(e) => Err(Self::error_response("Failed to save cohort", e)),
+        }
+    }
+
+    /// List saved cohorts
+    pub async fn list(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<CohortDefinition>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list(actor).await {
+            Ok(cohorts) => Ok(Json(cohorts)),
+            Err(e) => Err(Self::error_response("Failed to list cohorts", e)),
+        }
+    }
+
+    /// Count the patients meeting cohort criteria
+    pub async fn preview(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+        Json(payload): Json<CohortPreviewRequest>,
+    ) -> Result<Json<CohortPreview>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.preview(&payload.criteria, actor).await {
+            Ok(preview) => Ok(Json(preview)),
+            Err(e) => Err(Self::error_response("Failed to preview cohort", e)),
+        }
+    }
+
+    /// Get a saved cohort
+    pub async fn get(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<CohortDefinition>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.get(id, actor).await {
+            Ok(Some(cohort)) => Ok(Json(cohort)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve cohort", e)),
+        }
+    }
+
+    /// Replace a cohort's definition
+    pub async fn update(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<CohortRequest>,
+    ) -> Result<Json<CohortDefinition>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.update(id, payload, actor).await {
+            Ok(Some(cohort)) => Ok(Json(cohort)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update cohort", e)),
+        }
+    }
+
+    /// Retire a cohort
+    pub async fn delete(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.delete(id, actor).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retire cohort", e)),
+        }
+    }
+
+    /// Evaluate a cohort into its member list
+    pub async fn evaluate(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<CohortEvaluation>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.evaluate(id, actor).await {
+            Ok(Some(evaluation)) => Ok(Json(evaluation)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to evaluate cohort", e)),
+        }
+    }
+
+    /// List a cohort's members as of its latest evaluation
+    pub async fn members(
+        State(service): State<Arc<CohortService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Query(query): Query<CohortMembersQuery>,
+    ) -> Result<Json<Vec<CohortMember>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.members(id, &query, actor).await {
+            Ok(Some(members)) => Ok(Json(members)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to list cohort members", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(id: Uuid) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Cohort not found".to_string(),
+                message: format!("Cohort with id {} not found", id),
+            }),
+        )
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
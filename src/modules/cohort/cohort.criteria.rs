@@ -0,0 +1,457 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::models::constants::fhir::{LOINC_SYSTEM, SNOMED_CT_SYSTEM};
+use crate::models::VitalSignKind;
+use crate::utils::fhir_search::TokenParam;
+
+/// Deepest nesting of combinators a definition may use
+const MAX_DEPTH: usize = 10;
+
+/// Most criteria, combinators included, one definition may hold
+const MAX_CRITERIA: usize = 100;
+
+/// Most codes one criterion may list
+const MAX_CODES: usize = 200;
+
+/// Longest look-back of a criterion, about a hundred years
+const MAX_DAYS: u32 = 36_500;
+
+const GENDERS: [&str; 4] = ["male", "female", "other", "unknown"];
+
+/// How a lab value is compared with a criterion's threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparator {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+}
+
+impl Comparator {
+    fn operator(&self) -> &'static str {
+        match self {
+            Comparator::Gt => ">",
+            Comparator::Ge => ">=",
+            Comparator::Lt => "<",
+            Comparator::Le => "<=",
+            Comparator::Eq => "=",
+        }
+    }
+}
+
+/// A condition patients of a cohort meet: a boolean combination of
+/// demographic, diagnosis, lab value and medication criteria. Codes are
+/// tokens as in FHIR search, `system|code`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CohortCriterion {
+    /// Every one of the criteria
+    All { criteria: Vec<CohortCriterion> },
+    /// At least one of the criteria
+    Any { criteria: Vec<CohortCriterion> },
+    /// Not the criterion
+    Not { criterion: Box<CohortCriterion> },
+    /// Administrative gender, one of those listed
+    Gender { genders: Vec<String> },
+    /// Age in whole years today; patients without a birth date have none
+    Age { min: Option<u32>, max: Option<u32> },
+    Deceased { deceased: bool },
+    /// A condition with one of the codes, neither refuted nor entered in
+    /// error; with `active_only`, still active, and with `within_days`,
+    /// with an onset (or if none, recorded) in that many days
+    Diagnosis {
+        codes: Vec<String>,
+        #[serde(default)]
+        active_only: bool,
+        within_days: Option<u32>,
+    },
+    /// A result of the LOINC code, among lab observations and vital signs,
+    /// meeting the comparison; with `latest`, the most recent result must
+    /// meet it
+    LabValue {
+        code: String,
+        comparator: Comparator,
+        value: f64,
+        within_days: Option<u32>,
+        #[serde(default)]
+        latest: bool,
+    },
+    /// A prescription of one of the codes, by its coded medication or the
+    /// medication it references; with `active_only`, still active
+    Medication {
+        codes: Vec<String>,
+        #[serde(default)]
+        active_only: bool,
+        within_days: Option<u32>,
+    },
+}
+
+/// A code token, which must name a code
+fn code_token(code: &str) -> Result<TokenParam, String> {
+    let token = TokenParam::parse(code.trim()).map_err(|e| e.to_string())?;
+    if token.code.is_none() {
+        return Err(format!("{:?} names no code", code));
+    }
+    Ok(token)
+}
+
+/// The token as a JSON array of one coding, to match a CodeableConcept's
+/// `coding` by containment
+fn coding(token: &TokenParam) -> Value {
+    let mut coding = Map::new();
+    if let Some(system) = token.system.as_deref().filter(|system| !system.is_empty()) {
+        coding.insert("system".to_string(), system.into());
+    }
+    if let Some(code) = &token.code {
+        coding.insert("code".to_string(), code.as_str().into());
+    }
+    Value::Array(vec![coding.into()])
+}
+
+fn check_codes(kind: &str, codes: &[String]) -> Result<(), String> {
+    if codes.is_empty() || codes.len() > MAX_CODES {
+        return Err(format!("A {} criterion lists 1 to {} codes", kind, MAX_CODES));
+    }
+    for code in codes {
+        code_token(code)?;
+    }
+    Ok(())
+}
+
+fn check_days(within_days: Option<u32>) -> Result<(), String> {
+    if within_days.is_some_and(|days| days == 0 || days > MAX_DAYS) {
+        return Err(format!("within_days is 1 to {}", MAX_DAYS));
+    }
+    Ok(())
+}
+
+/// Condition that a timestamp column falls within the last days, if any
+fn push_within(query: &mut QueryBuilder<'_, Postgres>, column: &str, within_days: Option<u32>) {
+    if let Some(days) = within_days {
+        query
+            .push(format!(" AND {} >= NOW() - make_interval(days => ", column))
+            .push_bind(days as i32)
+            .push(")");
+    }
+}
+
+impl CohortCriterion {
+    /// Check the definition's shape and every criterion in it
+    pub fn check(&self) -> Result<(), String> {
+        let mut count = 0;
+        self.check_at(1, &mut count)
+    }
+
+    fn check_at(&self, depth: usize, count: &mut usize) -> Result<(), String> {
+        *count += 1;
+        if depth > MAX_DEPTH {
+            return Err(format!("Criteria nest at most {} deep", MAX_DEPTH));
+        }
+        if *count > MAX_CRITERIA {
+            return Err(format!("A cohort has at most {} criteria", MAX_CRITERIA));
+        }
+        match self {
+            CohortCriterion::All { criteria } | CohortCriterion::Any { criteria } => {
+                if criteria.is_empty() {
+                    return Err("all and any need at least one criterion".to_string());
+                }
+                for criterion in criteria {
+                    criterion.check_at(depth + 1, count)?;
+                }
+            }
+            CohortCriterion::Not { criterion } => criterion.check_at(depth + 1, count)?,
+            CohortCriterion::Gender { genders } => {
+                if genders.is_empty() {
+                    return Err("A gender criterion lists at least one gender".to_string());
+                }
+                if let Some(gender) = genders.iter().find(|gender| !GENDERS.contains(&gender.as_str())) {
+                    return Err(format!("{} is not a gender; genders are {}", gender, GENDERS.join(", ")));
+                }
+            }
+            CohortCriterion::Age { min, max } => {
+                if min.is_none() && max.is_none() {
+                    return Err("An age criterion needs a min, a max or both".to_string());
+                }
+                let beyond = min.iter().chain(max.iter()).any(|age| *age > 150);
+                if beyond || min.zip(*max).is_some_and(|(min, max)| min > max) {
+                    return Err("An age criterion's min and max are 0 to 150, min first".to_string());
+                }
+            }
+            CohortCriterion::Deceased { .. } => {}
+            CohortCriterion::Diagnosis { codes, within_days, .. } => {
+                check_codes("diagnosis", codes)?;
+                check_days(*within_days)?;
+            }
+            CohortCriterion::LabValue { code, value, within_days, .. } => {
+                let token = code_token(code)?;
+                if token.system.as_deref().is_some_and(|system| system != LOINC_SYSTEM) {
+                    return Err(format!("Lab values are matched by LOINC code, not {}", code));
+                }
+                if !value.is_finite() {
+                    return Err("A lab value criterion needs a number to compare with".to_string());
+                }
+                check_days(*within_days)?;
+            }
+            CohortCriterion::Medication { codes, within_days, .. } => {
+                check_codes("medication", codes)?;
+                check_days(*within_days)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Push the criterion as a condition on patients aliased `p`. The
+    /// criterion must have been checked.
+    pub fn push_condition(&self, query: &mut QueryBuilder<'_, Postgres>) -> Result<(), String> {
+        match self {
+            CohortCriterion::All { criteria } | CohortCriterion::Any { criteria } => {
+                let joiner = if matches!(self, CohortCriterion::All { .. }) { " AND " } else { " OR " };
+                query.push("(");
+                for (i, criterion) in criteria.iter().enumerate() {
+                    if i > 0 {
+                        query.push(joiner);
+                    }
+                    criterion.push_condition(query)?;
+                }
+                query.push(")");
+            }
+            CohortCriterion::Not { criterion } => {
+                query.push("NOT ");
+                criterion.push_condition(query)?;
+            }
+            CohortCriterion::Gender { genders } => {
+                query.push("p.gender = ANY(").push_bind(genders.clone()).push(")");
+            }
+            // Ages are bounds on the indexed birth date: at least min years
+            // old is born min years ago or earlier, at most max years old is
+            // born less than max + 1 years ago
+            CohortCriterion::Age { min, max } => {
+                query.push("(p.birth_date IS NOT NULL");
+                if let Some(min) = min {
+                    query
+                        .push(" AND p.birth_date <= (CURRENT_DATE - make_interval(years => ")
+                        .push_bind(*min as i32)
+                        .push("))::date");
+                }
+                if let Some(max) = max {
+                    query
+                        .push(" AND p.birth_date > (CURRENT_DATE - make_interval(years => ")
+                        .push_bind(*max as i32 + 1)
+                        .push("))::date");
+                }
+                query.push(")");
+            }
+            CohortCriterion::Deceased { deceased } => {
+                query.push("COALESCE(p.deceased, false) = ").push_bind(*deceased);
+            }
+            CohortCriterion::Diagnosis { codes, active_only, within_days } => {
+                query.push(
+                    "EXISTS (SELECT 1 FROM conditions c WHERE c.patient_id = p.id AND c.deleted_at IS NULL \
+                     AND c.verification_status NOT IN ('refuted', 'entered-in-error')",
+                );
+                if *active_only {
+                    query.push(" AND c.clinical_status IN ('active', 'recurrence', 'relapse')");
+                }
+                push_within(query, "COALESCE(c.onset_date_time, c.recorded_date)", *within_days);
+                query.push(" AND (false");
+                for code in codes {
+                    let token = code_token(code)?;
+                    match (token.system.as_deref(), &token.code) {
+                        // SNOMED CT concepts are matched on their indexed column
+                        (Some(SNOMED_CT_SYSTEM), Some(code)) => {
+                            query.push(" OR c.snomed_code = ").push_bind(code.clone());
+                        }
+                        _ => {
+                            query.push(" OR c.code->'coding' @> ").push_bind(coding(&token));
+                        }
+                    }
+                }
+                query.push("))");
+            }
+            CohortCriterion::LabValue { code, comparator, value, within_days, latest } => {
+                let token = code_token(code)?;
+                let code = token.code.clone().unwrap_or_default();
+                if *latest {
+                    query.push("(SELECT r.value FROM (");
+                    Self::push_results(query, &code, *within_days);
+                    query
+                        .push(") r ORDER BY r.at DESC NULLS LAST LIMIT 1) ")
+                        .push(comparator.operator())
+                        .push(" ")
+                        .push_bind(*value);
+                } else {
+                    query.push("EXISTS (SELECT 1 FROM (");
+                    Self::push_results(query, &code, *within_days);
+                    query.push(") r WHERE r.value ").push(comparator.operator()).push(" ").push_bind(*value).push(")");
+                }
+            }
+            CohortCriterion::Medication { codes, active_only, within_days } => {
+                query.push(
+                    "EXISTS (SELECT 1 FROM medication_requests mr LEFT JOIN medications m \
+                     ON m.id = mr.medication_reference WHERE mr.subject = p.id",
+                );
+                if *active_only {
+                    query.push(" AND mr.status = 'active'");
+                } else {
+                    query.push(" AND mr.status NOT IN ('entered-in-error', 'cancelled', 'draft')");
+                }
+                push_within(query, "mr.authored_on", *within_days);
+                query.push(" AND (false");
+                for code in codes {
+                    let coding = coding(&code_token(code)?);
+                    query
+                        .push(" OR mr.medication_codeable_concept->'coding' @> ")
+                        .push_bind(coding.clone())
+                        .push(" OR m.code->'coding' @> ")
+                        .push_bind(coding);
+                }
+                query.push("))");
+            }
+        }
+        Ok(())
+    }
+
+    /// Numeric results of a LOINC code for the patient, as `value` and
+    /// `at`: quantities of lab observations, and vital signs, blood
+    /// pressure's components included
+    fn push_results(query: &mut QueryBuilder<'_, Postgres>, code: &str, within_days: Option<u32>) {
+        query
+            .push(
+                "SELECT CASE WHEN jsonb_typeof(o.value_quantity->'value') = 'number' \
+                 THEN (o.value_quantity->>'value')::float8 END AS value, o.effective_datetime AS at \
+                 FROM observations o WHERE o.subject = p.id AND o.status IN ('final', 'amended', 'corrected') \
+                 AND o.code->'coding' @> ",
+            )
+            .push_bind(coding(&TokenParam { system: Some(LOINC_SYSTEM.to_string()), code: Some(code.to_string()) }));
+        push_within(query, "o.effective_datetime", within_days);
+
+        let component = VitalSignKind::from_loinc_code(code).filter(|kind| {
+            matches!(kind, VitalSignKind::SystolicBloodPressure | VitalSignKind::DiastolicBloodPressure)
+        });
+        match component {
+            Some(kind) => {
+                query
+                    .push(
+                        " UNION ALL SELECT (vc.component->'value'->>'value')::float8, v.effective_date_time \
+                         FROM vital_signs v CROSS JOIN LATERAL jsonb_array_elements(v.component) vc(component) \
+                         WHERE v.patient_id = p.id AND v.deleted_at IS NULL \
+                         AND v.status IN ('final', 'amended', 'corrected') AND vc.component->>'kind' = ",
+                    )
+                    .push_bind(kind.as_str());
+            }
+            None => {
+                query
+                    .push(
+                        " UNION ALL SELECT v.value, v.effective_date_time FROM vital_signs v \
+                         WHERE v.patient_id = p.id AND v.deleted_at IS NULL AND v.value IS NOT NULL \
+                         AND v.status IN ('final', 'amended', 'corrected') AND v.loinc_code = ",
+                    )
+                    .push_bind(code.to_string());
+            }
+        }
+        push_within(query, "v.effective_date_time", within_days);
+    }
+
+    /// Check the criterion and push a query of the active patients meeting
+    /// it, selecting their IDs as `patient_id`
+    pub fn push_patients(&self, query: &mut QueryBuilder<'_, Postgres>) -> Result<(), String> {
+        self.check()?;
+        query.push("SELECT p.id AS patient_id FROM patients p WHERE p.active = true AND ");
+        self.push_condition(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(criterion: &CohortCriterion) -> String {
+        let mut query = QueryBuilder::new("");
+        criterion.push_patients(&mut query).unwrap();
+        query.sql().to_string()
+    }
+
+    fn diabetic_adults() -> CohortCriterion {
+        serde_json::from_value(serde_json::json!({
+            "type": "all",
+            "criteria": [
+                {"type": "age", "min": 18},
+                {"type": "diagnosis", "active_only": true,
+                 "codes": ["http://snomed.info/sct|44054006", "http://hl7.org/fhir/sid/icd-10-cm|E11.9"]},
+                {"type": "any", "criteria": [
+                    {"type": "lab_value", "code": "http://loinc.org|4548-4", "comparator": "gt", "value": 9.0,
+                     "latest": true},
+                    {"type": "not", "criterion": {"type": "medication", "codes": ["860975"], "within_days": 365}}
+                ]}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compile_criteria() {
+        let sql = compile(&diabetic_adults());
+        assert!(sql.starts_with("SELECT p.id AS patient_id FROM patients p WHERE p.active = true AND (("));
+        assert!(sql.contains("p.birth_date <= (CURRENT_DATE - make_interval(years => $1))::date"));
+        assert!(!sql.contains("p.birth_date >"));
+        assert!(sql.contains("c.clinical_status IN ('active', 'recurrence', 'relapse')"));
+        assert!(sql.contains("OR c.snomed_code = $2 OR c.code->'coding' @> $3"));
+        assert!(sql.contains("ORDER BY r.at DESC NULLS LAST LIMIT 1) > $6"));
+        assert!(sql.contains("v.loinc_code = $5"));
+        assert!(sql.contains("NOT EXISTS (SELECT 1 FROM medication_requests mr"));
+        assert!(sql.contains("mr.authored_on >= NOW() - make_interval(days => $7)"));
+        assert!(sql.contains("mr.medication_codeable_concept->'coding' @> $8 OR m.code->'coding' @> $9"));
+        assert!(sql.ends_with(")))"));
+
+        let pressure = CohortCriterion::LabValue {
+            code: "8480-6".to_string(),
+            comparator: Comparator::Ge,
+            value: 140.0,
+            within_days: Some(90),
+            latest: false,
+        };
+        let sql = compile(&pressure);
+        assert!(sql.contains("jsonb_array_elements(v.component)"));
+        assert!(sql.contains("o.effective_datetime >= NOW() - make_interval(days => $2)"));
+        assert!(sql.ends_with(") r WHERE r.value >= $5)"));
+    }
+
+    #[test]
+    fn test_check_criteria() {
+        let age = CohortCriterion::Age { min: Some(65), max: Some(18) };
+        assert!(age.check().is_err());
+        let gender = CohortCriterion::Gender { genders: vec!["f".to_string()] };
+        assert!(gender.check().unwrap_err().contains("not a gender"));
+        assert!(CohortCriterion::Any { criteria: vec![] }.check().is_err());
+
+        let diagnosis = |code: &str| CohortCriterion::Diagnosis {
+            codes: vec![code.to_string()],
+            active_only: false,
+            within_days: None,
+        };
+        assert!(diagnosis("http://snomed.info/sct|").check().is_err());
+        assert!(diagnosis("http://snomed.info/sct|38341003").check().is_ok());
+
+        let lab = CohortCriterion::LabValue {
+            code: "http://snomed.info/sct|38341003".to_string(),
+            comparator: Comparator::Gt,
+            value: 1.0,
+            within_days: None,
+            latest: false,
+        };
+        assert!(lab.check().unwrap_err().contains("LOINC"));
+
+        let mut deep = CohortCriterion::Deceased { deceased: false };
+        for _ in 0..MAX_DEPTH {
+            deep = CohortCriterion::Not { criterion: Box::new(deep) };
+        }
+        assert!(deep.check().unwrap_err().contains("nest"));
+
+        let wide = CohortCriterion::Any { criteria: vec![CohortCriterion::Deceased { deceased: true }; MAX_CRITERIA] };
+        assert!(wide.check().unwrap_err().contains("at most"));
+    }
+}
@@ -0,0 +1,418 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, Transaction};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{ACT_CODE_SYSTEM, ICD_10_CM_SYSTEM, ICD_10_SYSTEM};
+use crate::modules::authorization::{Action, ConfidentialityLabel, PurposeOfUse, Subject};
+use crate::modules::cohort::cohort_criteria::CohortCriterion;
+use crate::modules::part2::Part2Consents;
+use crate::modules::role::RoleService;
+
+// Import SQL queries
+use crate::modules::cohort::cohort_sql::*;
+
+/// Longest an evaluation or preview of a cohort may run
+const STATEMENT_TIMEOUT: &str = "120s";
+
+/// Largest page of a cohort's members
+const MAX_MEMBERS_PAGE: u32 = 1_000;
+
+/// A saved cohort and the outcome of its latest evaluation
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortDefinition {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub criteria: CohortCriterion,
+    /// Members at the latest evaluation; `None` if never evaluated
+    pub member_count: Option<i32>,
+    pub last_evaluated_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A cohort to save
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohortRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub criteria: CohortCriterion,
+}
+
+impl CohortRequest {
+    /// Check the cohort and put it in its stored form
+    pub fn check(&mut self) -> Result<(), String> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() {
+            return Err("A cohort needs a name".to_string());
+        }
+        self.criteria.check()
+    }
+}
+
+/// Criteria to count the patients of, without saving them
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohortPreviewRequest {
+    pub criteria: CohortCriterion,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortPreview {
+    pub member_count: i64,
+}
+
+/// Outcome of evaluating a cohort into its member list
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortEvaluation {
+    pub cohort_id: Uuid,
+    pub member_count: i32,
+    /// Patients who joined the cohort
+    pub added: i64,
+    /// Patients who no longer meet its criteria
+    pub removed: i64,
+    pub evaluated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortMember {
+    pub patient_id: Uuid,
+    /// When the patient joined the cohort
+    pub added_at: DateTime<Utc>,
+}
+
+/// A page of a cohort's members
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CohortMembersQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+fn cohort_from_row(row: &PgRow) -> Result<CohortDefinition, HimsError> {
+    let criteria = serde_json::from_value(row.get("criteria")).map_err(|e| HimsError::InternalError {
+        message: format!("Stored cohort criteria are not valid: {}", e),
+    })?;
+    Ok(CohortDefinition {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        criteria,
+        member_count: row.get("member_count"),
+        last_evaluated_at: row.get("last_evaluated_at"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+fn criteria_value(criteria: &CohortCriterion) -> Result<serde_json::Value, HimsError> {
+    serde_json::to_value(criteria).map_err(|e| HimsError::InternalError {
+        message: format!("Failed to serialize cohort criteria: {}", e),
+    })
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+/// A cohort's query that ran out of time is the cohort's fault, not the
+/// server's
+fn evaluation_error(e: sqlx::Error) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.code().as_deref() == Some("57014") => HimsError::ValidationError {
+            message: format!("The cohort's criteria took longer than {} to evaluate", STATEMENT_TIMEOUT),
+        },
+        _ => database_error(e),
+    }
+}
+
+/// Cohort service: population health cohorts defined by demographic,
+/// diagnosis, lab value and medication criteria, compiled to one query
+/// against the clinical tables and kept as member lists for recalls,
+/// research and quality measures
+pub struct CohortService {
+    pool: PgPool,
+    role_service: RoleService,
+    part2: Part2Consents,
+}
+
+impl CohortService {
+    /// Create new cohort service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            part2: Part2Consents::new(pool.clone()),
+            pool,
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// A cohort is changed by whoever defined it, or with the configure
+    /// permission
+    async fn require_owner(&self, cohort: &CohortDefinition, actor: Uuid) -> Result<(), HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        if cohort.created_by != Some(actor) {
+            self.require(actor, &[Action::Configure]).await?;
+        }
+        Ok(())
+    }
+
+    /// Save a cohort
+    pub async fn create(&self, mut request: CohortRequest, actor: Uuid) -> Result<CohortDefinition, HimsError> {
+        request.check().map_err(validation_error)?;
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+
+        let row = sqlx::query(INSERT_COHORT)
+            .bind(Uuid::new_v4())
+            .bind(&request.name)
+            .bind(&request.description)
+            .bind(criteria_value(&request.criteria)?)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("A cohort named {} already exists", request.name)))?;
+        let cohort = cohort_from_row(&row)?;
+
+        tracing::info!("Cohort {} ({}) saved by {}", cohort.name, cohort.id, actor);
+        Ok(cohort)
+    }
+
+    /// Replace a cohort's definition
+    pub async fn update(
+        &self,
+        id: Uuid,
+        mut request: CohortRequest,
+        actor: Uuid,
+    ) -> Result<Option<CohortDefinition>, HimsError> {
+        request.check().map_err(validation_error)?;
+        let Some(existing) = self.get(id, actor).await? else {
+            return Ok(None);
+        };
+        self.require_owner(&existing, actor).await?;
+
+        let row = sqlx::query(UPDATE_COHORT)
+            .bind(id)
+            .bind(&request.name)
+            .bind(&request.description)
+            .bind(criteria_value(&request.criteria)?)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("A cohort named {} already exists", request.name)))?;
+
+        tracing::info!("Cohort {} updated by {}", id, actor);
+        row.as_ref().map(cohort_from_row).transpose()
+    }
+
+    /// Retire a cohort and drop its members. Returns false if there was no
+    /// such cohort.
+    pub async fn delete(&self, id: Uuid, actor: Uuid) -> Result<bool, HimsError> {
+        let Some(existing) = self.get(id, actor).await? else {
+            return Ok(false);
+        };
+        self.require_owner(&existing, actor).await?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let result = sqlx::query(DEACTIVATE_COHORT)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        sqlx::query(DELETE_COHORT_MEMBERS)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+
+        tracing::info!("Cohort {} retired by {}", id, actor);
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get a cohort
+    pub async fn get(&self, id: Uuid, actor: Uuid) -> Result<Option<CohortDefinition>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        let row = sqlx::query(GET_COHORT_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        row.as_ref().map(cohort_from_row).transpose()
+    }
+
+    /// Saved cohorts, by name
+    pub async fn list(&self, actor: Uuid) -> Result<Vec<CohortDefinition>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        let rows = sqlx::query(LIST_COHORTS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        rows.iter().map(cohort_from_row).collect()
+    }
+
+    /// Count the patients meeting criteria, to try a cohort out before
+    /// saving it
+    pub async fn preview(&self, criteria: &CohortCriterion, actor: Uuid) -> Result<CohortPreview, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT count(*) AS member_count FROM (");
+        criteria.push_patients(&mut query).map_err(validation_error)?;
+        query.push(") cohort");
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        self.limit_statement_time(&mut tx).await?;
+        let row = query.build().fetch_one(&mut *tx).await.map_err(evaluation_error)?;
+        tx.commit().await.map_err(database_error)?;
+
+        Ok(CohortPreview { member_count: row.get("member_count") })
+    }
+
+    /// Evaluate a cohort's criteria into its member list. Patients who
+    /// still meet them keep the time they joined; the cohort is locked, so
+    /// evaluations of one cohort take turns.
+    pub async fn evaluate(&self, id: Uuid, actor: Uuid) -> Result<Option<CohortEvaluation>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics, Action::Search]).await?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(row) = sqlx::query(LOCK_COHORT)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(database_error)?
+        else {
+            return Ok(None);
+        };
+        let cohort = cohort_from_row(&row)?;
+        self.limit_statement_time(&mut tx).await?;
+
+        let mut query = QueryBuilder::<Postgres>::new("WITH matches AS (");
+        cohort.criteria.push_patients(&mut query).map_err(validation_error)?;
+        query
+            .push("), removed AS (DELETE FROM cohort_members m WHERE m.cohort_id = ")
+            .push_bind(id)
+            .push(
+                " AND NOT EXISTS (SELECT 1 FROM matches WHERE matches.patient_id = m.patient_id) RETURNING 1), \
+                 added AS (INSERT INTO cohort_members (cohort_id, patient_id) SELECT ",
+            )
+            .push_bind(id)
+            .push(
+                ", patient_id FROM matches ON CONFLICT DO NOTHING RETURNING 1) \
+                 SELECT (SELECT count(*) FROM matches)::int AS member_count, \
+                 (SELECT count(*) FROM added) AS added, (SELECT count(*) FROM removed) AS removed",
+            );
+        let row = query.build().fetch_one(&mut *tx).await.map_err(evaluation_error)?;
+        let member_count: i32 = row.get("member_count");
+        let evaluated_at: DateTime<Utc> = sqlx::query_scalar(SET_COHORT_EVALUATED)
+            .bind(id)
+            .bind(member_count)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+
+        let evaluation = CohortEvaluation {
+            cohort_id: id,
+            member_count,
+            added: row.get("added"),
+            removed: row.get("removed"),
+            evaluated_at,
+        };
+        tracing::info!(
+            "Cohort {} evaluated by {}: {} members, {} added, {} removed",
+            id,
+            actor,
+            evaluation.member_count,
+            evaluation.added,
+            evaluation.removed
+        );
+        Ok(Some(evaluation))
+    }
+
+    /// A page of a cohort's members as of its latest evaluation. Members
+    /// holding Part 2 records are withheld without their consent for the
+    /// user to use them in research, so a page may come up short.
+    pub async fn members(
+        &self,
+        id: Uuid,
+        page: &CohortMembersQuery,
+        actor: Uuid,
+    ) -> Result<Option<Vec<CohortMember>>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics, Action::Search]).await?;
+        let limit = page.limit.unwrap_or(100);
+        if limit == 0 || limit > MAX_MEMBERS_PAGE {
+            return Err(validation_error(format!("limit is 1 to {}", MAX_MEMBERS_PAGE)));
+        }
+        if self.get(id, actor).await?.is_none() {
+            return Ok(None);
+        }
+
+        let part2_label = json!([{ "system": ACT_CODE_SYSTEM, "code": ConfidentialityLabel::SubstanceAbuse.code() }]);
+        let rows = sqlx::query(LIST_COHORT_MEMBERS)
+            .bind(id)
+            .bind(limit as i64)
+            .bind(page.offset.unwrap_or(0) as i64)
+            .bind(part2_label)
+            .bind([ICD_10_SYSTEM, ICD_10_CM_SYSTEM])
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        // Membership of a patient with Part 2 records may reveal them, so
+        // those members are left out unless the patient consented to
+        // research
+        let patients: Vec<Uuid> = rows.iter().map(|row| row.get("patient_id")).collect();
+        let disclosures = self
+            .part2
+            .disclosures(&patients, &Subject::User(actor), PurposeOfUse::Research)
+            .await?;
+        let members: Vec<CohortMember> = rows
+            .iter()
+            .filter(|row| {
+                let patient_id: Uuid = row.get("patient_id");
+                disclosures
+                    .get(&patient_id)
+                    .is_some_and(|disclosure| disclosure.discloses(row.get("part2")))
+            })
+            .map(|row| CohortMember { patient_id: row.get("patient_id"), added_at: row.get("added_at") })
+            .collect();
+        if members.len() < rows.len() {
+            let withheld = rows.len() - members.len();
+            tracing::info!("Cohort {} members shown to {}: {} withheld under Part 2", id, actor, withheld);
+        }
+        Ok(Some(members))
+    }
+
+    async fn limit_statement_time(&self, tx: &mut Transaction<'_, Postgres>) -> Result<(), HimsError> {
+        sqlx::query(SET_STATEMENT_TIMEOUT)
+            .bind(STATEMENT_TIMEOUT)
+            .execute(&mut **tx)
+            .await
+            .map_err(database_error)?;
+        Ok(())
+    }
+}
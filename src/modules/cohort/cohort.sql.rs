@@ -0,0 +1,94 @@
+//! Cohort SQL Queries
+//!
+//! This file contains all SQL queries used by the cohort service. Queries
+//! of a cohort's patients are compiled from its criteria.
+
+/// Insert a cohort definition
+pub const INSERT_COHORT: &str = r#"
+    INSERT INTO cohort_definitions (id, name, description, criteria, created_by)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING id, name, description, criteria, member_count, last_evaluated_at, created_by, created_at, updated_at
+"#;
+
+/// Get an active cohort definition by ID
+pub const GET_COHORT_BY_ID: &str = r#"
+    SELECT id, name, description, criteria, member_count, last_evaluated_at, created_by, created_at, updated_at
+    FROM cohort_definitions
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Lock an active cohort definition while it is evaluated
+pub const LOCK_COHORT: &str = r#"
+    SELECT id, name, description, criteria, member_count, last_evaluated_at, created_by, created_at, updated_at
+    FROM cohort_definitions
+    WHERE id = $1 AND is_active = true
+    FOR UPDATE
+"#;
+
+/// Active cohort definitions, by name
+pub const LIST_COHORTS: &str = r#"
+    SELECT id, name, description, criteria, member_count, last_evaluated_at, created_by, created_at, updated_at
+    FROM cohort_definitions
+    WHERE is_active = true
+    ORDER BY name
+"#;
+
+/// Replace an active cohort's definition; its members stand until it is
+/// evaluated again
+pub const UPDATE_COHORT: &str = r#"
+    UPDATE cohort_definitions
+    SET name = $2, description = $3, criteria = $4
+    WHERE id = $1 AND is_active = true
+    RETURNING id, name, description, criteria, member_count, last_evaluated_at, created_by, created_at, updated_at
+"#;
+
+/// Retire a cohort definition
+pub const DEACTIVATE_COHORT: &str = r#"
+    UPDATE cohort_definitions
+    SET is_active = false
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Drop the members of a retired cohort
+pub const DELETE_COHORT_MEMBERS: &str = r#"
+    DELETE FROM cohort_members WHERE cohort_id = $1
+"#;
+
+/// Record an evaluation of a cohort
+pub const SET_COHORT_EVALUATED: &str = r#"
+    UPDATE cohort_definitions
+    SET member_count = $2, last_evaluated_at = NOW()
+    WHERE id = $1
+    RETURNING last_evaluated_at
+"#;
+
+/// A page of a cohort's members, as they joined, and whether they hold
+/// Part 2 records: any labelled `$4` among the records criteria match on,
+/// or a condition coded in `$5` as a substance use disorder (F10-F19 less
+/// F17)
+pub const LIST_COHORT_MEMBERS: &str = r#"
+    SELECT m.patient_id, m.added_at,
+           EXISTS (
+               SELECT 1 FROM conditions c
+               WHERE c.patient_id = m.patient_id AND c.deleted_at IS NULL
+               AND (c.meta->'security' @> $4 OR EXISTS (
+                   SELECT 1 FROM jsonb_array_elements(c.code->'coding') cc(coding)
+                   WHERE cc.coding->>'system' = ANY($5)
+                   AND upper(cc.coding->>'code') ~ '^F1[0-689]'
+               ))
+           )
+           OR EXISTS (SELECT 1 FROM medication_requests mr WHERE mr.subject = m.patient_id AND mr.meta->'security' @> $4)
+           OR EXISTS (SELECT 1 FROM observations o WHERE o.subject = m.patient_id AND o.meta->'security' @> $4)
+           OR EXISTS (
+               SELECT 1 FROM vital_signs v
+               WHERE v.patient_id = m.patient_id AND v.deleted_at IS NULL AND v.meta->'security' @> $4
+           ) AS part2
+    FROM cohort_members m
+    WHERE m.cohort_id = $1
+    ORDER BY m.added_at, m.patient_id
+    LIMIT $2 OFFSET $3
+"#;
+
+/// Limit the statement time of an evaluation, for the rest of its
+/// transaction
+pub const SET_STATEMENT_TIMEOUT: &str = "SELECT set_config('statement_timeout', $1, true)";
@@ -0,0 +1,54 @@
+//! Cohort Module
+//!
+//! This module builds population health cohorts including:
+//! - Boolean combinations of demographic, diagnosis, lab value and medication criteria
+//! - Criteria compiled to one query against the clinical tables, previewed as a count
+//! - Saved definitions evaluated into member lists for recalls, research and quality measures
+//! - Members holding 42 CFR Part 2 records listed only with their consent to research
+
+#[path = "cohort.controller.rs"]
+pub mod cohort_controller;
+#[path = "cohort.service.rs"]
+pub mod cohort_service;
+#[path = "cohort.criteria.rs"]
+pub mod cohort_criteria;
+#[path = "cohort.sql.rs"]
+pub mod cohort_sql;
+
+pub use cohort_controller::CohortController;
+pub use cohort_criteria::{CohortCriterion, Comparator};
+pub use cohort_service::{CohortDefinition, CohortEvaluation, CohortService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Cohort Module Configuration
+pub struct CohortModule {
+    pub service: Arc<CohortService>,
+    pub controller: Arc<CohortController>,
+}
+
+impl CohortModule {
+    /// Create a new Cohort Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(CohortService::new(db_pool));
+        let controller = Arc::new(CohortController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<CohortService> {
+        self.service.clone()
+    }
+}
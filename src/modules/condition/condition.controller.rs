@@ -0,0 +1,396 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::exporters::CcdaProblemSection;
+use crate::models::constants::{
+    CONDITION_CATEGORY_SYSTEM, CONDITION_CLINICAL_STATUS_SYSTEM, CONDITION_RESOURCE_TYPE,
+    CONDITION_VERIFICATION_STATUS_SYSTEM,
+};
+use crate::models::{CodeableConcept, Coding, Condition, Reference, ResourceMeta};
+use crate::modules::condition::condition_service::{ConditionRequest, ConditionSearch, ConditionSearchResult};
+use crate::modules::auth::AuthContext;
+use crate::modules::condition::ConditionService;
+use crate::modules::part2::{add_security_labels, no_redisclosure_labels};
+use crate::utils::api_router::ApiRouter;
+use crate::utils::etag::{if_match_version, precondition_status, versioned, Versioned};
+use crate::utils::fhir_search::{EntrySearch, SearchEntryMode};
+use crate::utils::pagination::{page_links, BundleLink};
+
+/// FHIR R4 Condition as served
+#[derive(Debug, Serialize)]
+pub struct ConditionResponse {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    #[serde(rename = "clinicalStatus")]
+    pub clinical_status: CodeableConcept,
+    #[serde(rename = "verificationStatus")]
+    pub verification_status: CodeableConcept,
+    pub category: Vec<CodeableConcept>,
+    pub code: CodeableConcept,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<CodeableConcept>,
+    pub subject: Reference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "onsetDateTime")]
+    pub onset_date_time: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "abatementDateTime")]
+    pub abatement_date_time: Option<DateTime<Utc>>,
+    #[serde(rename = "recordedDate")]
+    pub recorded_date: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorder: Option<Reference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+/// FHIR Annotation, as Condition.note carries it
+#[derive(Debug, Serialize)]
+pub struct Annotation {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConditionBundle {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub link: Vec<BundleLink>,
+    pub entry: Vec<ConditionBundleEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConditionBundleEntry {
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    pub resource: ConditionResponse,
+    /// Set in searchsets only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<EntrySearch>,
+}
+
+/// Options of a problem list read
+#[derive(Debug, Default, Deserialize)]
+pub struct ProblemListQuery {
+    /// Also list inactive, remitted and resolved problems
+    #[serde(default)]
+    pub include_abated: bool,
+    /// Who the list is disclosed to, for matching 42 CFR Part 2 consents;
+    /// the user reading it unless given
+    pub recipient: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Condition controller for FHIR R4 Conditions and patients' problem lists.
+/// Every handler acts as the signed-in user, whose permissions the service
+/// checks and whose reads it audits and segments under 42 CFR Part 2.
+pub struct ConditionController {
+    condition_service: Arc<ConditionService>,
+}
+
+impl ConditionController {
+    /// Create new controller with injected service
+    pub fn new(condition_service: Arc<ConditionService>) -> Self {
+        Self { condition_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_condition, "Record a condition")
+            .get("/", Self::search_conditions, "Search conditions with query parameters")
+            .get("/:id", Self::get_condition, "Get condition by ID")
+            .put("/:id", Self::update_condition, "Update condition")
+            .delete("/:id", Self::delete_condition, "Delete condition")
+            .with_state(self.condition_service.clone())
+    }
+
+    /// Routes under a patient, merged into the patient routes
+    pub fn problem_list_routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/:id/problem-list", Self::problem_list, "Get a patient's problem list")
+            .get("/:id/problem-list/ccda", Self::problem_list_ccda, "Export a patient's problem list as a C-CDA Problems section")
+            .with_state(self.condition_service.clone())
+    }
+
+    /// Record a condition
+    pub async fn create_condition(
+        State(service): State<Arc<ConditionService>>,
+        auth: AuthContext,
+        Json(request): Json<ConditionRequest>,
+    ) -> Result<(StatusCode, Versioned<ConditionResponse>), ErrorReply> {
+        tracing::info!("Recording condition for patient {}", request.patient_id);
+
+        match service.create(request, &auth).await {
+            Ok(condition) => {
+                tracing::info!("Condition recorded successfully: {}", condition.id);
+                Ok((StatusCode::CREATED, versioned(&condition.meta.clone(), Self::condition_to_response(condition))))
+            }
+            Err(e) => Err(Self::error_response("Failed to record condition", e)),
+        }
+    }
+
+    /// Get condition by ID
+    pub async fn get_condition(
+        State(service): State<Arc<ConditionService>>,
+        auth: AuthContext,
+        Path(id): Path<Uuid>,
+    ) -> Result<Versioned<ConditionResponse>, ErrorReply> {
+        match service.read(id, &auth).await {
+            Ok(Some(condition)) => Ok(versioned(&condition.meta.clone(), Self::condition_to_response(condition))),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to get condition", e)),
+        }
+    }
+
+    /// Search conditions with query parameters. Part 2 conditions no
+    /// consent covers are left out of the page.
+    pub async fn search_conditions(
+        State(service): State<Arc<ConditionService>>,
+        auth: AuthContext,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<Json<ConditionBundle>, ErrorReply> {
+        tracing::info!("Searching conditions with params: {:?}", params);
+
+        let search = ConditionSearch::from_params(&params)
+            .map_err(|e| Self::error_response("Invalid search parameters", e))?;
+        match service.search_as(&search, &auth).await {
+            Ok((mut result, segmented)) => {
+                tracing::info!("Found {} conditions", segmented.conditions.len());
+                let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+                result.conditions = segmented.conditions;
+                let mut bundle = Self::conditions_to_bundle(result, link);
+                if segmented.part2 {
+                    add_security_labels(&mut bundle.meta.security, no_redisclosure_labels());
+                }
+                Ok(Json(bundle))
+            }
+            Err(e) => Err(Self::error_response("Failed to search conditions", e)),
+        }
+    }
+
+    /// Update condition; `If-Match` must name the version being replaced
+    pub async fn update_condition(
+        State(service): State<Arc<ConditionService>>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ConditionRequest>,
+    ) -> Result<Versioned<ConditionResponse>, ErrorReply> {
+        tracing::info!("Updating condition: {}", id);
+
+        let expected_version = if_match_version(&headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the condition's ETag in If-Match to update it".to_string(),
+                }),
+            )
+        })?;
+
+        match service.update(id, request, &expected_version, &auth).await {
+            Ok(Some(condition)) => Ok(versioned(&condition.meta.clone(), Self::condition_to_response(condition))),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update condition", e)),
+        }
+    }
+
+    /// Delete condition
+    pub async fn delete_condition(
+        State(service): State<Arc<ConditionService>>,
+        auth: AuthContext,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Deleting condition: {}", id);
+
+        match service.delete(id, &auth).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to delete condition", e)),
+        }
+    }
+
+    /// The patient's problem list as a collection Bundle, current problems
+    /// first. Part 2 substance use records are included only under a
+    /// consent for the recipient and the declared purpose of use, labelled
+    /// against redisclosure.
+    pub async fn problem_list(
+        State(service): State<Arc<ConditionService>>,
+        auth: AuthContext,
+        Path(patient_id): Path<Uuid>,
+        Query(query): Query<ProblemListQuery>,
+    ) -> Result<Json<ConditionBundle>, ErrorReply> {
+        match service
+            .read_problem_list(patient_id, query.include_abated, query.recipient.as_deref(), &auth)
+            .await
+        {
+            Ok(segmented) => {
+                let mut bundle = Self::bundle("collection", segmented.conditions, None, Vec::new(), None);
+                if segmented.part2 {
+                    add_security_labels(&mut bundle.meta.security, no_redisclosure_labels());
+                }
+                Ok(Json(bundle))
+            }
+            Err(e) => Err(Self::error_response("Failed to get problem list", e)),
+        }
+    }
+
+    /// The patient's problem list as a C-CDA Problems section, for
+    /// inclusion in a CCD
+    pub async fn problem_list_ccda(
+        State(service): State<Arc<ConditionService>>,
+        auth: AuthContext,
+        Path(patient_id): Path<Uuid>,
+        Query(query): Query<ProblemListQuery>,
+    ) -> Result<([(HeaderName, &'static str); 1], String), ErrorReply> {
+        match service
+            .read_problem_list(patient_id, query.include_abated, query.recipient.as_deref(), &auth)
+            .await
+        {
+            Ok(segmented) if segmented.part2 => Ok((
+                [(header::CONTENT_TYPE, "application/xml")],
+                CcdaProblemSection::generate_part2(&segmented.conditions),
+            )),
+            Ok(segmented) => Ok((
+                [(header::CONTENT_TYPE, "application/xml")],
+                CcdaProblemSection::generate(&segmented.conditions),
+            )),
+            Err(e) => Err(Self::error_response("Failed to export problem list", e)),
+        }
+    }
+
+    /// Convert Condition model to FHIR response format
+    pub fn condition_to_response(condition: Condition) -> ConditionResponse {
+        let coded = |system: &str, code: &str| CodeableConcept {
+            coding: vec![Coding {
+                system: Some(system.to_string()),
+                version: None,
+                code: Some(code.to_string()),
+                display: None,
+            }],
+            text: None,
+        };
+
+        ConditionResponse {
+            resource_type: CONDITION_RESOURCE_TYPE.to_string(),
+            id: condition.id,
+            meta: condition.meta,
+            clinical_status: coded(CONDITION_CLINICAL_STATUS_SYSTEM, condition.clinical_status.as_str()),
+            verification_status: coded(CONDITION_VERIFICATION_STATUS_SYSTEM, condition.verification_status.as_str()),
+            category: vec![coded(CONDITION_CATEGORY_SYSTEM, condition.category.as_str())],
+            code: condition.code,
+            severity: condition.severity,
+            subject: Reference {
+                reference: format!("Patient/{}", condition.patient_id),
+                display: None,
+            },
+            encounter: condition.encounter_id.map(|id| Reference {
+                reference: format!("Encounter/{}", id),
+                display: None,
+            }),
+            onset_date_time: condition.onset,
+            abatement_date_time: condition.abatement,
+            recorded_date: condition.recorded_date,
+            recorder: condition.recorder,
+            note: condition.note.into_iter().map(|text| Annotation { text }).collect(),
+        }
+    }
+
+    /// Convert a page of search results to a FHIR searchset Bundle
+    fn conditions_to_bundle(result: ConditionSearchResult, link: Vec<BundleLink>) -> ConditionBundle {
+        let search = EntrySearch {
+            mode: SearchEntryMode::Match,
+        };
+        Self::bundle("searchset", result.conditions, result.total, link, Some(search))
+    }
+
+    fn bundle(
+        bundle_type: &str,
+        conditions: Vec<Condition>,
+        total: Option<i64>,
+        link: Vec<BundleLink>,
+        search: Option<EntrySearch>,
+    ) -> ConditionBundle {
+        let entry = conditions
+            .into_iter()
+            .map(|condition| ConditionBundleEntry {
+                full_url: format!("Condition/{}", condition.id),
+                resource: Self::condition_to_response(condition),
+                search: search.clone(),
+            })
+            .collect();
+
+        ConditionBundle {
+            resource_type: "Bundle".to_string(),
+            id: Uuid::new_v4(),
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: Utc::now(),
+                profile: vec!["http://hl7.org/fhir/StructureDefinition/Bundle".to_string()],
+                security: vec![],
+                tag: vec![],
+            },
+            bundle_type: bundle_type.to_string(),
+            total,
+            link,
+            entry,
+        }
+    }
+
+    fn not_found(id: Uuid) -> ErrorReply {
+        tracing::warn!("Condition not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Condition not found".to_string(),
+                message: format!("Condition with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => precondition_status(&e).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,835 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::SNOMED_CT_SYSTEM;
+use crate::models::{
+    AuditAction, AuditEventType, AuditLog, CodeableConcept, Condition, ConditionCategory, ConditionClinicalStatus,
+    ConditionVerificationStatus, Reference,
+};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, PurposeOfUse, Subject};
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::modules::part2::{Part2Consents, Part2Disclosure, SegmentedConditions};
+use crate::modules::role::RoleService;
+use crate::standards::terminology::TerminologyService;
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{reference_id, split_modifier, DateParam, SearchParamDefinition, TokenParam, TotalMode};
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
+
+// Import SQL queries from separate file
+use crate::modules::condition::condition_sql::*;
+
+/// Condition from a row of any query selecting its columns
+fn condition_from_row(row: &PgRow) -> Condition {
+    Condition {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        clinical_status: ConditionClinicalStatus::from_string(row.get("clinical_status")),
+        verification_status: ConditionVerificationStatus::from_string(row.get("verification_status")),
+        category: ConditionCategory::from_string(row.get("category")),
+        code: serde_json::from_value(row.get("code")).unwrap_or(CodeableConcept {
+            coding: Vec::new(),
+            text: None,
+        }),
+        severity: row
+            .get::<Option<serde_json::Value>, _>("severity")
+            .and_then(|severity| serde_json::from_value(severity).ok()),
+        onset: row.get("onset_date_time"),
+        abatement: row.get("abatement_date_time"),
+        recorded_date: row.get("recorded_date"),
+        recorder: row
+            .get::<Option<serde_json::Value>, _>("recorder")
+            .and_then(|recorder| serde_json::from_value(recorder).ok()),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
+    }
+}
+
+/// Check a condition before it is saved and return its SNOMED CT concept:
+/// the code must carry a well-formed SNOMED CT concept, and an abated
+/// condition must be inactive, in remission or resolved (FHIR con-4) and
+/// must not abate before its onset
+pub fn check_condition(condition: &Condition) -> Result<String, HimsError> {
+    let invalid = |message: String| HimsError::ValidationError { message };
+
+    let snomed_code = condition
+        .snomed_code()
+        .ok_or_else(|| invalid(format!("Condition code needs a coding in {}", SNOMED_CT_SYSTEM)))?;
+    if !TerminologyService::is_snomed_concept_id(snomed_code) {
+        return Err(invalid(format!("{:?} is not a SNOMED CT concept identifier", snomed_code)));
+    }
+
+    if let Some(abatement) = condition.abatement {
+        if condition.clinical_status.is_active() {
+            return Err(invalid(format!(
+                "A condition with an abatement cannot be {}; use inactive, remission or resolved",
+                condition.clinical_status.as_str()
+            )));
+        }
+        if condition.onset.is_some_and(|onset| abatement < onset) {
+            return Err(invalid("Condition abatement is before its onset".to_string()));
+        }
+    }
+    Ok(snomed_code.to_string())
+}
+
+/// A condition as clients send it to record or replace one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    #[serde(default)]
+    pub clinical_status: ConditionClinicalStatus,
+    #[serde(default)]
+    pub verification_status: ConditionVerificationStatus,
+    #[serde(default)]
+    pub category: ConditionCategory,
+    pub code: CodeableConcept,
+    pub severity: Option<CodeableConcept>,
+    pub onset: Option<DateTime<Utc>>,
+    pub abatement: Option<DateTime<Utc>>,
+    pub recorder: Option<Reference>,
+    pub note: Option<String>,
+}
+
+impl From<ConditionRequest> for Condition {
+    fn from(request: ConditionRequest) -> Self {
+        let mut condition = Condition::new(request.patient_id, request.code, request.category);
+        condition.encounter_id = request.encounter_id;
+        condition.clinical_status = request.clinical_status;
+        condition.verification_status = request.verification_status;
+        condition.severity = request.severity;
+        condition.onset = request.onset;
+        condition.abatement = request.abatement;
+        condition.recorder = request.recorder;
+        condition.note = request.note;
+        condition
+    }
+}
+
+/// Page size of a condition search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 50;
+
+/// Largest page a condition search returns
+pub const MAX_SEARCH_COUNT: i64 = 200;
+
+/// Paging of condition searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Order of condition searches, most recently recorded first, as
+/// SEARCH_CONDITIONS keys its rows
+const SEARCH_KEYS: [SortKey; 1] = [SortKey::new("recorded_date", "timestamptz", true)];
+
+/// Parsed condition search
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionSearch {
+    pub patient: Vec<Uuid>,
+    pub encounter: Vec<Uuid>,
+    /// Clinical status codes; any matches
+    pub clinical_status: Vec<String>,
+    /// Verification status codes; any matches
+    pub verification_status: Vec<String>,
+    /// Category codes; any matches
+    pub category: Vec<String>,
+    /// Codes of the condition; any matches
+    pub code: Vec<TokenParam>,
+    pub onset_date: Vec<DateParam>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for ConditionSearch {
+    fn default() -> Self {
+        Self {
+            patient: Vec::new(),
+            encounter: Vec::new(),
+            clinical_status: Vec::new(),
+            verification_status: Vec::new(),
+            category: Vec::new(),
+            code: Vec::new(),
+            onset_date: Vec::new(),
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// A page of condition search results
+#[derive(Debug)]
+pub struct ConditionSearchResult {
+    pub conditions: Vec<Condition>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl ConditionSearch {
+    /// Search parameters `from_params` supports, for the CapabilityStatement
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "patient",
+            param_type: "reference",
+            documentation: "The patient the condition is about",
+        },
+        SearchParamDefinition {
+            name: "encounter",
+            param_type: "reference",
+            documentation: "The encounter the condition was recorded in",
+        },
+        SearchParamDefinition {
+            name: "clinical-status",
+            param_type: "token",
+            documentation: "Clinical status, e.g. active; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "verification-status",
+            param_type: "token",
+            documentation: "Verification status, e.g. confirmed; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "category",
+            param_type: "token",
+            documentation: "problem-list-item or encounter-diagnosis",
+        },
+        SearchParamDefinition {
+            name: "code",
+            param_type: "token",
+            documentation: "Code of the condition, as system|code or a bare code",
+        },
+        SearchParamDefinition {
+            name: "onset-date",
+            param_type: "date",
+            documentation: "Onset of the condition; supports eq, ne, gt, lt, ge and le prefixes",
+        },
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored; invalid
+    /// values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+        // Codes of serde-renamed status enums, checked against the enum
+        fn codes<T: serde::de::DeserializeOwned>(
+            value: &str,
+            invalid: impl Fn(&str) -> HimsError,
+        ) -> Result<Vec<String>, HimsError> {
+            value
+                .split(',')
+                .map(|code| {
+                    serde_json::from_value::<T>(serde_json::Value::String(code.to_string()))
+                        .map(|_| code.to_string())
+                        .map_err(|_| invalid(code))
+                })
+                .collect()
+        }
+
+        for (param, value) in params {
+            let (name, modifier) = split_modifier(param);
+            match (name, modifier) {
+                ("patient", None | Some("Patient")) => {
+                    for reference in value.split(',') {
+                        search.patient.push(reference_id(reference, "Patient")?);
+                    }
+                }
+                ("encounter", None | Some("Encounter")) => {
+                    for reference in value.split(',') {
+                        search.encounter.push(reference_id(reference, "Encounter")?);
+                    }
+                }
+                ("clinical-status", None) => search
+                    .clinical_status
+                    .extend(codes::<ConditionClinicalStatus>(value, |code| invalid(name, code))?),
+                ("verification-status", None) => search
+                    .verification_status
+                    .extend(codes::<ConditionVerificationStatus>(value, |code| invalid(name, code))?),
+                ("category", None) => search
+                    .category
+                    .extend(codes::<ConditionCategory>(value, |code| invalid(name, code))?),
+                ("code", None) => {
+                    for token in value.split(',') {
+                        search.code.push(TokenParam::parse(token)?);
+                    }
+                }
+                ("onset-date", None) => search.onset_date.push(DateParam::parse(value)?),
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported condition search parameter {}", param),
+            }
+        }
+        search.page.check_cursor(&SEARCH_KEYS)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on conditions for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if !self.patient.is_empty() {
+            query.push(" AND patient_id = ANY(").push_bind(self.patient.clone()).push(")");
+        }
+        if !self.encounter.is_empty() {
+            query.push(" AND encounter_id = ANY(").push_bind(self.encounter.clone()).push(")");
+        }
+        if !self.clinical_status.is_empty() {
+            query.push(" AND clinical_status = ANY(").push_bind(self.clinical_status.clone()).push(")");
+        }
+        if !self.verification_status.is_empty() {
+            query
+                .push(" AND verification_status = ANY(")
+                .push_bind(self.verification_status.clone())
+                .push(")");
+        }
+        if !self.category.is_empty() {
+            query.push(" AND category = ANY(").push_bind(self.category.clone()).push(")");
+        }
+        if !self.code.is_empty() {
+            query.push(" AND (false");
+            for token in &self.code {
+                match (token.system.as_deref(), &token.code) {
+                    // SNOMED CT concepts are matched on their indexed column
+                    (Some(SNOMED_CT_SYSTEM), Some(code)) => {
+                        query.push(" OR snomed_code = ").push_bind(code.clone());
+                    }
+                    (system, code) => {
+                        let mut coding = serde_json::Map::new();
+                        if let Some(system) = system.filter(|system| !system.is_empty()) {
+                            coding.insert("system".to_string(), system.into());
+                        }
+                        if let Some(code) = code {
+                            coding.insert("code".to_string(), code.as_str().into());
+                        }
+                        query
+                            .push(" OR code->'coding' @> ")
+                            .push_bind(serde_json::Value::Array(vec![coding.into()]));
+                    }
+                }
+            }
+            query.push(")");
+        }
+        for date in &self.onset_date {
+            date.push_condition(query, "onset_date_time");
+        }
+    }
+}
+
+/// Condition service for problems and diagnoses, and the per-patient
+/// problem list they make up
+#[derive(Clone)]
+pub struct ConditionService {
+    pool: PgPool,
+    events: EventBus,
+    part2: Part2Consents,
+    role_service: Arc<RoleService>,
+    audit_service: Arc<AuditService>,
+}
+
+impl ConditionService {
+    /// Create new condition service
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_events(pool, EventBus::default())
+    }
+
+    /// Create the service publishing to a shared event bus
+    pub fn with_events(pool: PgPool, events: EventBus) -> Self {
+        Self {
+            part2: Part2Consents::new(pool.clone()),
+            role_service: Arc::new(RoleService::new(pool.clone())),
+            audit_service: Arc::new(AuditService::new(pool.clone())),
+            pool,
+            events,
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Record an access to or change of a patient's conditions
+    async fn audit(
+        &self,
+        auth: &AuthContext,
+        (event_type, action): (AuditEventType, AuditAction),
+        patient_id: Uuid,
+        condition_id: Option<Uuid>,
+        details: String,
+    ) -> Result<(), HimsError> {
+        let mut audit_log = auth
+            .audit(AuditLog::new(event_type, action, "Condition".to_string()))
+            .with_patient(patient_id)
+            .with_details(details);
+        if let Some(condition_id) = condition_id {
+            audit_log = audit_log.with_resource(condition_id);
+        }
+        self.audit_service.create_audit_log(&audit_log).await?;
+        Ok(())
+    }
+
+    /// What a disclosure of the patient's records to the user may include,
+    /// for the purpose of use they declare
+    async fn disclosure(&self, patient_id: Uuid, auth: &AuthContext) -> Result<Part2Disclosure, HimsError> {
+        let recipient = Subject::User(auth.user_id).to_string();
+        self.part2.disclosure(patient_id, Some(&recipient), auth.purpose_of_use).await
+    }
+
+    /// The conditions the user may be shown: Part 2 conditions only under a
+    /// consent covering the user and their purpose, labelled against
+    /// redisclosure, and none of a labelled patient without one
+    async fn disclosed(&self, conditions: Vec<Condition>, auth: &AuthContext) -> Result<SegmentedConditions, HimsError> {
+        let mut disclosures = HashMap::new();
+        let mut segmented = SegmentedConditions {
+            conditions: Vec::with_capacity(conditions.len()),
+            withheld: 0,
+            part2: false,
+        };
+        for condition in conditions {
+            let patient_id = condition.patient_id;
+            let disclosure = match disclosures.get(&patient_id) {
+                Some(disclosure) => *disclosure,
+                None => {
+                    let disclosure = self.disclosure(patient_id, auth).await?;
+                    disclosures.insert(patient_id, disclosure);
+                    disclosure
+                }
+            };
+            match disclosure.segment(vec![condition]) {
+                Ok(one) => {
+                    segmented.withheld += one.withheld;
+                    segmented.part2 |= one.part2;
+                    segmented.conditions.extend(one.conditions);
+                }
+                Err(HimsError::SecurityError { .. }) => segmented.withheld += 1,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(segmented)
+    }
+
+    /// Record a condition as the user of `auth`
+    pub async fn create(&self, request: ConditionRequest, auth: &AuthContext) -> Result<Condition, HimsError> {
+        self.require(auth.user_id, &[Action::Create]).await?;
+        let condition: Condition = request.into();
+        let snomed_code = check_condition(&condition)?;
+
+        sqlx::query(INSERT_CONDITION)
+            .bind(condition.id)
+            .bind(condition.patient_id)
+            .bind(condition.encounter_id)
+            .bind(condition.clinical_status.as_str())
+            .bind(condition.verification_status.as_str())
+            .bind(condition.category.as_str())
+            .bind(serde_json::to_value(&condition.code).unwrap_or_default())
+            .bind(&snomed_code)
+            .bind(condition.severity.as_ref().and_then(|severity| serde_json::to_value(severity).ok()))
+            .bind(condition.onset)
+            .bind(condition.abatement)
+            .bind(condition.recorded_date)
+            .bind(condition.recorder.as_ref().and_then(|recorder| serde_json::to_value(recorder).ok()))
+            .bind(&condition.note)
+            .bind(condition.created_at)
+            .bind(condition.updated_at)
+            .bind(serde_json::to_value(&condition.meta).unwrap_or_default())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.audit(
+            auth,
+            (AuditEventType::Create, AuditAction::Create),
+            condition.patient_id,
+            Some(condition.id),
+            "Condition recorded".to_string(),
+        )
+        .await?;
+        self.events.publish(DomainEvent::ConditionRecorded {
+            condition_id: condition.id,
+            patient_id: condition.patient_id,
+        });
+        Ok(condition)
+    }
+
+    /// Read a condition as the user of `auth`. A Part 2 condition, or any
+    /// condition of a labelled patient, needs a consent covering the user.
+    pub async fn read(&self, id: Uuid, auth: &AuthContext) -> Result<Option<Condition>, HimsError> {
+        self.require(auth.user_id, &[Action::Read]).await?;
+        let Some(condition) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let patient_id = condition.patient_id;
+        let mut segmented = self.disclosure(patient_id, auth).await?.segment(vec![condition])?;
+        let Some(condition) = segmented.conditions.pop() else {
+            return Err(HimsError::SecurityError {
+                message: format!(
+                    "Condition {} is protected by 42 CFR Part 2; disclosing it needs the patient's consent",
+                    id
+                ),
+            });
+        };
+
+        self.audit(
+            auth,
+            (AuditEventType::Access, AuditAction::Read),
+            patient_id,
+            Some(id),
+            "Condition read".to_string(),
+        )
+        .await?;
+        Ok(Some(condition))
+    }
+
+    /// Get condition by ID. Not authorized, segmented or audited; services
+    /// looking conditions up for their own resources use it, and requests
+    /// read through `read`.
+    pub async fn get(&self, id: Uuid) -> Result<Option<Condition>, HimsError> {
+        let row = sqlx::query(GET_CONDITION_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(row.as_ref().map(condition_from_row))
+    }
+
+    /// Search conditions, a page at a time
+    pub async fn search(&self, search: &ConditionSearch) -> Result<ConditionSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_CONDITIONS);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &SEARCH_KEYS);
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut fetched = Vec::new();
+        for row in rows {
+            let condition = condition_from_row(&row);
+            fetched.push((condition, RowKey::from_column(row.get("page_key"))?));
+        }
+        let page = search.page.page(fetched);
+
+        let total = count_matches(
+            &self.pool,
+            search.page.total(),
+            COUNT_CONDITIONS,
+            ESTIMATE_CONDITIONS,
+            |query| search.push_filters(query),
+        )
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(ConditionSearchResult {
+            conditions: page.items,
+            total,
+            next: page.next,
+            previous: page.previous,
+        })
+    }
+
+    /// Search conditions as the user of `auth`, leaving out those a Part 2
+    /// disclosure to them may not include. Each patient whose conditions
+    /// are returned has the search audited.
+    pub async fn search_as(
+        &self,
+        search: &ConditionSearch,
+        auth: &AuthContext,
+    ) -> Result<(ConditionSearchResult, SegmentedConditions), HimsError> {
+        self.require(auth.user_id, &[Action::Read]).await?;
+        let mut result = self.search(search).await?;
+        let segmented = self.disclosed(std::mem::take(&mut result.conditions), auth).await?;
+        if segmented.withheld > 0 {
+            tracing::info!("Withheld {} Part 2 conditions from user {}", segmented.withheld, auth.user_id);
+        }
+
+        let mut returned: Vec<(Uuid, usize)> = Vec::new();
+        for condition in &segmented.conditions {
+            match returned.iter_mut().find(|(patient_id, _)| *patient_id == condition.patient_id) {
+                Some((_, count)) => *count += 1,
+                None => returned.push((condition.patient_id, 1)),
+            }
+        }
+        for (patient_id, count) in returned {
+            self.audit(
+                auth,
+                (AuditEventType::Access, AuditAction::Read),
+                patient_id,
+                None,
+                format!("Condition search: {} conditions returned", count),
+            )
+            .await?;
+        }
+        Ok((result, segmented))
+    }
+
+    /// Replace a condition if it is still at `expected_version`, or `None`
+    /// if there is no such condition. The condition stays about the same
+    /// patient.
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: ConditionRequest,
+        expected_version: &str,
+        auth: &AuthContext,
+    ) -> Result<Option<Condition>, HimsError> {
+        self.require(auth.user_id, &[Action::Update]).await?;
+        let Some(current) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let current_version = current.meta.version_id.as_deref().unwrap_or("1");
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Condition {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+        if request.patient_id != current.patient_id {
+            return Err(HimsError::ValidationError {
+                message: format!("Condition {} is about patient {}", id, current.patient_id),
+            });
+        }
+
+        let condition: Condition = request.into();
+        let snomed_code = check_condition(&condition)?;
+        let rows_affected = sqlx::query(UPDATE_CONDITION)
+            .bind(id)
+            .bind(condition.encounter_id)
+            .bind(condition.clinical_status.as_str())
+            .bind(condition.verification_status.as_str())
+            .bind(condition.category.as_str())
+            .bind(serde_json::to_value(&condition.code).unwrap_or_default())
+            .bind(&snomed_code)
+            .bind(condition.severity.as_ref().and_then(|severity| serde_json::to_value(severity).ok()))
+            .bind(condition.onset)
+            .bind(condition.abatement)
+            .bind(condition.recorder.as_ref().and_then(|recorder| serde_json::to_value(recorder).ok()))
+            .bind(&condition.note)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Condition {} was modified concurrently", id),
+            });
+        }
+        self.audit(
+            auth,
+            (AuditEventType::Update, AuditAction::Update),
+            current.patient_id,
+            Some(id),
+            format!("Condition updated from version {}", expected_version),
+        )
+        .await?;
+        self.events.publish(DomainEvent::ConditionUpdated { condition_id: id });
+
+        self.get(id).await
+    }
+
+    /// Soft delete condition
+    pub async fn delete(&self, id: Uuid, auth: &AuthContext) -> Result<bool, HimsError> {
+        self.require(auth.user_id, &[Action::Delete]).await?;
+        let Some(current) = self.get(id).await? else {
+            return Ok(false);
+        };
+        let rows_affected = sqlx::query(SOFT_DELETE_CONDITION)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected > 0 {
+            self.audit(
+                auth,
+                (AuditEventType::Delete, AuditAction::Delete),
+                current.patient_id,
+                Some(id),
+                "Condition deleted".to_string(),
+            )
+            .await?;
+            self.events.publish(DomainEvent::ConditionDeleted { condition_id: id });
+        }
+        Ok(rows_affected > 0)
+    }
+
+    /// The patient's problem list, current problems first; abated problems
+    /// are included only when `include_abated` is set
+    pub async fn problem_list(&self, patient_id: Uuid, include_abated: bool) -> Result<Vec<Condition>, HimsError> {
+        let rows = sqlx::query(GET_PROBLEM_LIST)
+            .bind(patient_id)
+            .bind(include_abated)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(rows.iter().map(condition_from_row).collect())
+    }
+
+    /// The patient's problem list as disclosed to `recipient` for `purpose`,
+    /// leaving out substance use records no 42 CFR Part 2 consent covers
+    pub async fn problem_list_disclosure(
+        &self,
+        patient_id: Uuid,
+        include_abated: bool,
+        recipient: Option<&str>,
+        purpose: Option<PurposeOfUse>,
+    ) -> Result<SegmentedConditions, HimsError> {
+        let disclosure = self.part2.disclosure(patient_id, recipient, purpose).await?;
+        let segmented = disclosure.segment(self.problem_list(patient_id, include_abated).await?)?;
+        if segmented.withheld > 0 {
+            tracing::info!(
+                "Withheld {} Part 2 conditions of patient {} from disclosure without consent",
+                segmented.withheld,
+                patient_id
+            );
+        }
+        Ok(segmented)
+    }
+
+    /// The patient's problem list read by the user of `auth` and disclosed
+    /// to `recipient`, the user unless named, for their purpose of use
+    pub async fn read_problem_list(
+        &self,
+        patient_id: Uuid,
+        include_abated: bool,
+        recipient: Option<&str>,
+        auth: &AuthContext,
+    ) -> Result<SegmentedConditions, HimsError> {
+        self.require(auth.user_id, &[Action::Read]).await?;
+        let user = Subject::User(auth.user_id).to_string();
+        let recipient = recipient.unwrap_or(&user);
+        let segmented = self
+            .problem_list_disclosure(patient_id, include_abated, Some(recipient), auth.purpose_of_use)
+            .await?;
+        self.audit(
+            auth,
+            (AuditEventType::Access, AuditAction::Read),
+            patient_id,
+            None,
+            format!("Problem list disclosed to {}: {} conditions", recipient, segmented.conditions.len()),
+        )
+        .await?;
+        Ok(segmented)
+    }
+
+    /// SNOMED CT concepts of the patient's active problems, for decision
+    /// support rules keyed on diagnoses
+    pub async fn active_problem_codes(&self, patient_id: Uuid) -> Result<Vec<String>, HimsError> {
+        Ok(self
+            .problem_list(patient_id, false)
+            .await?
+            .iter()
+            .filter_map(|condition| condition.snomed_code().map(str::to_string))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Coding;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn diabetes() -> Condition {
+        let code = CodeableConcept {
+            coding: vec![Coding {
+                system: Some(SNOMED_CT_SYSTEM.to_string()),
+                version: None,
+                code: Some("73211009".to_string()),
+                display: Some("Diabetes mellitus".to_string()),
+            }],
+            text: None,
+        };
+        Condition::new(Uuid::new_v4(), code, ConditionCategory::ProblemListItem)
+    }
+
+    #[test]
+    fn test_check_condition() {
+        let mut condition = diabetes();
+        assert_eq!(check_condition(&condition).unwrap(), "73211009");
+        assert!(condition.is_active_problem());
+
+        let onset = Utc::now() - chrono::Duration::days(30);
+        condition.onset = Some(onset);
+        condition.abatement = Some(onset + chrono::Duration::days(7));
+        assert!(check_condition(&condition).is_err(), "abated but active");
+        condition.resolve(onset - chrono::Duration::days(1));
+        assert!(check_condition(&condition).is_err(), "abated before onset");
+        condition.resolve(onset + chrono::Duration::days(7));
+        assert!(check_condition(&condition).is_ok());
+        assert!(!condition.is_active_problem());
+
+        let mut condition = diabetes();
+        condition.code.coding[0].code = Some("73211008".to_string());
+        assert!(check_condition(&condition).is_err(), "bad check digit");
+        condition.code.coding[0].system = Some("http://hl7.org/fhir/sid/icd-10".to_string());
+        condition.code.coding[0].code = Some("E11.9".to_string());
+        assert!(check_condition(&condition).is_err(), "no SNOMED CT coding");
+    }
+
+    #[test]
+    fn test_search_from_params() {
+        let patient = Uuid::new_v4();
+        let search = ConditionSearch::from_params(&params(&[
+            ("patient", format!("Patient/{}", patient).as_str()),
+            ("clinical-status", "active,relapse"),
+            ("category", "problem-list-item"),
+            ("code", "http://snomed.info/sct|73211009,E11.9"),
+            ("onset-date", "ge2020"),
+        ]))
+        .unwrap();
+        assert_eq!(search.patient, vec![patient]);
+        assert_eq!(search.clinical_status, vec!["active".to_string(), "relapse".to_string()]);
+        assert_eq!(search.code.len(), 2);
+
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM conditions WHERE deleted_at IS NULL");
+        ConditionSearch::from_params(&params(&[("code", "http://snomed.info/sct|73211009,E11.9")]))
+            .unwrap()
+            .push_filters(&mut query);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM conditions WHERE deleted_at IS NULL AND (false OR snomed_code = $1 OR code->'coding' @> $2)"
+        );
+
+        assert!(ConditionSearch::from_params(&params(&[("clinical-status", "cured")])).is_err());
+        assert!(ConditionSearch::from_params(&params(&[("patient", "Practitioner/1")])).is_err());
+    }
+
+    #[test]
+    fn test_advertised_params_are_supported() {
+        let id = Uuid::new_v4();
+        for param in ConditionSearch::SEARCH_PARAMS {
+            let value = match param.name {
+                "clinical-status" => "active".to_string(),
+                "verification-status" => "confirmed".to_string(),
+                "category" => "encounter-diagnosis".to_string(),
+                "code" => "73211009".to_string(),
+                "onset-date" => "2024-01".to_string(),
+                _ => id.to_string(),
+            };
+            let search = ConditionSearch::from_params(&params(&[(param.name, value.as_str())])).unwrap();
+            assert_ne!(search, ConditionSearch::default(), "{} was ignored", param.name);
+        }
+    }
+}
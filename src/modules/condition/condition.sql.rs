@@ -0,0 +1,88 @@
+//! Condition SQL Queries
+//! 
+//! This file contains all SQL queries used by the condition service.
+
+/// Insert a new condition
+pub const INSERT_CONDITION: &str = r#"
+    INSERT INTO conditions (
+        id, patient_id, encounter_id, clinical_status, verification_status, category,
+        code, snomed_code, severity, onset_date_time, abatement_date_time, recorded_date,
+        recorder, note, created_at, updated_at, meta
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17
+    )
+"#;
+
+/// Get condition by ID
+pub const GET_CONDITION_BY_ID: &str = r#"
+    SELECT id, patient_id, encounter_id, clinical_status, verification_status, category,
+           code, severity, onset_date_time, abatement_date_time, recorded_date,
+           recorder, note, created_at, updated_at, meta
+    FROM conditions
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Get the current version of a condition
+pub const GET_CONDITION_VERSION: &str = r#"
+    SELECT COALESCE(meta->>'version_id', '1')
+    FROM conditions
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Replace a condition if it is still at version $14, moving it to version
+/// $13. The patient a condition is about never changes.
+pub const UPDATE_CONDITION: &str = r#"
+    UPDATE conditions
+    SET encounter_id = $2, clinical_status = $3, verification_status = $4, category = $5,
+        code = $6, snomed_code = $7, severity = $8, onset_date_time = $9,
+        abatement_date_time = $10, recorder = $11, note = $12, updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($13::text))
+    WHERE id = $1 AND deleted_at IS NULL AND COALESCE(meta->>'version_id', '1') = $14
+"#;
+
+/// Soft delete condition
+pub const SOFT_DELETE_CONDITION: &str = r#"
+    UPDATE conditions
+    SET deleted_at = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// A patient's problem list: current problems first, each group by onset,
+/// most recent first. Refuted and entered-in-error problems are never
+/// listed; abated ones only when $2 is true.
+pub const GET_PROBLEM_LIST: &str = r#"
+    SELECT id, patient_id, encounter_id, clinical_status, verification_status, category,
+           code, severity, onset_date_time, abatement_date_time, recorded_date,
+           recorder, note, created_at, updated_at, meta
+    FROM conditions
+    WHERE patient_id = $1 AND deleted_at IS NULL
+      AND category = 'problem-list-item'
+      AND verification_status NOT IN ('refuted', 'entered-in-error')
+      AND ($2 OR clinical_status IN ('active', 'recurrence', 'relapse'))
+    ORDER BY clinical_status IN ('active', 'recurrence', 'relapse') DESC,
+             COALESCE(onset_date_time, recorded_date) DESC, id
+"#;
+
+/// Start of a condition search, most recently recorded first; the service
+/// appends the filters and the page's cursor, order and limit
+pub const SEARCH_CONDITIONS: &str = r#"
+    SELECT id, patient_id, encounter_id, clinical_status, verification_status, category,
+           code, severity, onset_date_time, abatement_date_time, recorded_date,
+           recorder, note, created_at, updated_at, meta,
+           ARRAY[recorded_date::text, id::text] AS page_key
+    FROM conditions
+    WHERE deleted_at IS NULL
+"#;
+
+/// Start of an accurate condition search total
+pub const COUNT_CONDITIONS: &str = r#"
+    SELECT COUNT(*) FROM conditions WHERE deleted_at IS NULL
+"#;
+
+/// Query plan of a condition search, whose row estimate answers
+/// `_total=estimate`
+pub const ESTIMATE_CONDITIONS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM conditions WHERE deleted_at IS NULL
+"#;
@@ -0,0 +1,58 @@
+//! Condition Module
+//!
+//! This module provides problems and diagnoses as FHIR R4 Conditions:
+//! - SNOMED CT coded conditions with onset and abatement
+//! - Clinical and verification status, per FHIR condition-clinical and
+//!   condition-ver-status
+//! - Encounter diagnoses linked to the encounter they were made in
+//! - A managed problem list per patient
+//! - Versioned updates guarded by `If-Match`
+//! - Permission checks and audit on every read and change, with 42 CFR
+//!   Part 2 conditions disclosed only under consent
+
+#[path = "condition.controller.rs"]
+pub mod condition_controller;
+#[path = "condition.service.rs"]
+pub mod condition_service;
+#[path = "condition.sql.rs"]
+pub mod condition_sql;
+
+pub use condition_controller::ConditionController;
+pub use condition_service::{ConditionRequest, ConditionSearch, ConditionSearchResult, ConditionService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::events::EventBus;
+use crate::utils::api_router::ApiRouter;
+
+/// Condition Module Configuration
+pub struct ConditionModule {
+    pub service: Arc<ConditionService>,
+    pub controller: Arc<ConditionController>,
+}
+
+impl ConditionModule {
+    /// Create a new Condition Module with dependency injection
+    pub fn new(db_pool: PgPool, events: EventBus) -> Self {
+        let service = Arc::new(ConditionService::with_events(db_pool, events));
+        let controller = Arc::new(ConditionController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Problem list routes, mounted under the patient routes
+    pub fn problem_list_routes(&self) -> ApiRouter {
+        self.controller.problem_list_routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<ConditionService> {
+        self.service.clone()
+    }
+}
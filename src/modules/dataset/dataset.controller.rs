@@ -0,0 +1,112 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::dataset::dataset_service::{DatasetQuery, DatasetResource};
+use crate::modules::dataset::DatasetService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Dataset controller for columnar exports of FHIR resources
+pub struct DatasetController {
+    dataset_service: Arc<DatasetService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl DatasetController {
+    /// Create new controller with injected service
+    pub fn new(dataset_service: Arc<DatasetService>) -> Self {
+        Self { dataset_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/resources", Self::resources, "List the resource types datasets hold and their columns")
+            .get("/export", Self::export, "Export resources as a Parquet dataset")
+            .with_state(self.dataset_service.clone())
+    }
+
+    /// List the resource types datasets hold and their columns
+    pub async fn resources(
+        State(service): State<Arc<DatasetService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<DatasetResource>>, ErrorReply> {
+        Self::actor(&headers)?;
+        Ok(Json(service.resources()))
+    }
+
+    /// Export resources as a Parquet dataset: a ZIP archive of a Parquet
+    /// file per resource type and day, at `resource_type=<type>/date=<day>/`
+    pub async fn export(
+        State(service): State<Arc<DatasetService>>,
+        headers: HeaderMap,
+        Query(query): Query<DatasetQuery>,
+    ) -> Result<Response, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.export(&query, actor).await {
+            Ok(export) => {
+                let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", export.file_name))
+                    .unwrap_or(HeaderValue::from_static("attachment"));
+                Ok((
+                    [
+                        (header::CONTENT_TYPE, HeaderValue::from_static("application/zip")),
+                        (header::CONTENT_DISPOSITION, disposition),
+                        (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                        (header::CACHE_CONTROL, HeaderValue::from_static("private, no-store")),
+                    ],
+                    export.bytes,
+                )
+                    .into_response())
+            }
+            Err(e) => Err(Self::error_response("Failed to export dataset", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConfigurationError { .. } => StatusCode::NOT_IMPLEMENTED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
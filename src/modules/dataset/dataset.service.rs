@@ -0,0 +1,315 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::exporters::columnar::{partition_resources, DatasetPartition, ResourceSpec, DATASET_RESOURCES};
+use crate::modules::authorization::{Action, PurposeOfUse, Subject};
+use crate::modules::condition::{ConditionController, ConditionSearch, ConditionService};
+use crate::modules::part2::{is_part2_row, Part2Consents};
+use crate::modules::patient::{PatientController, PatientSearch, PatientService};
+use crate::modules::role::RoleService;
+use crate::modules::vital_sign::{VitalSignController, VitalSignSearch, VitalSignService};
+use crate::utils::fhir_search::DateParam;
+
+/// Page size asked of each search; lowered to the search's largest page
+const PAGE_SIZE: &str = "1000";
+
+/// Resource types and period a dataset covers
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DatasetQuery {
+    /// Comma-separated resource types, by default every type datasets hold
+    #[serde(rename = "type")]
+    pub resource_types: Option<String>,
+    /// First day, in UTC; unbounded if not given
+    pub from: Option<NaiveDate>,
+    /// Last day, in UTC; unbounded if not given
+    pub to: Option<NaiveDate>,
+}
+
+impl DatasetQuery {
+    /// The specs of the resource types asked for
+    pub fn specs(&self) -> Result<Vec<&'static ResourceSpec>, String> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(format!("from ({}) is after to ({})", from, to));
+            }
+        }
+        let Some(types) = self.resource_types.as_deref().filter(|types| !types.trim().is_empty()) else {
+            return Ok(DATASET_RESOURCES.to_vec());
+        };
+        let mut specs: Vec<&'static ResourceSpec> = Vec::new();
+        for resource_type in types.split(',').map(str::trim) {
+            let spec = ResourceSpec::find(resource_type).ok_or_else(|| {
+                let supported: Vec<&str> = DATASET_RESOURCES.iter().map(|spec| spec.resource_type).collect();
+                format!("Datasets cannot hold {}; they hold {}", resource_type, supported.join(", "))
+            })?;
+            if !specs.iter().any(|known| known.resource_type == spec.resource_type) {
+                specs.push(spec);
+            }
+        }
+        Ok(specs)
+    }
+}
+
+/// A dataset, as a ZIP archive of Parquet files
+#[derive(Debug, Clone)]
+pub struct DatasetExport {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// A resource type a dataset can hold
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetResource {
+    pub resource_type: &'static str,
+    pub columns: Vec<DatasetColumn>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetColumn {
+    pub name: &'static str,
+    /// The FHIR element the column is read from
+    pub path: &'static str,
+}
+
+fn to_value<T: Serialize>(resource: T) -> Result<Value, HimsError> {
+    serde_json::to_value(resource).map_err(|e| HimsError::InternalError {
+        message: format!("Failed to serialize resource: {}", e),
+    })
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+/// Dataset service: FHIR resources flattened into columns and exported as
+/// Parquet files partitioned by resource type and date, for data scientists
+/// to read with no ETL of their own
+pub struct DatasetService {
+    role_service: RoleService,
+    patients: PatientService,
+    conditions: ConditionService,
+    vital_signs: VitalSignService,
+    part2: Part2Consents,
+}
+
+impl DatasetService {
+    /// Create new dataset service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            patients: PatientService::new(pool.clone()),
+            conditions: ConditionService::new(pool.clone()),
+            vital_signs: VitalSignService::new(pool.clone()),
+            part2: Part2Consents::new(pool),
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The resource types datasets hold, and their columns
+    pub fn resources(&self) -> Vec<DatasetResource> {
+        DATASET_RESOURCES
+            .iter()
+            .map(|spec| DatasetResource {
+                resource_type: spec.resource_type,
+                columns: spec
+                    .columns
+                    .iter()
+                    .map(|column| DatasetColumn { name: column.name, path: column.path })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Export the resources of a period as a Parquet dataset. Part 2
+    /// records are withheld without the patient's consent for the exporting
+    /// user to use them in research.
+    pub async fn export(&self, query: &DatasetQuery, actor: Uuid) -> Result<DatasetExport, HimsError> {
+        self.require(actor, &[Action::ResearchAccess, Action::ExportData]).await?;
+        let specs = query.specs().map_err(validation_error)?;
+        if cfg!(not(feature = "parquet")) {
+            return Err(Self::parquet_unavailable());
+        }
+
+        let mut fetched: Vec<(&ResourceSpec, Vec<(Uuid, Value)>)> = Vec::new();
+        for spec in &specs {
+            fetched.push((spec, self.fetch(spec.resource_type, query).await?));
+        }
+
+        // Part 2 records, and every record of a labelled patient, stay out
+        // of the dataset unless the patient consented to research
+        let patients: HashSet<Uuid> =
+            fetched.iter().flat_map(|(_, resources)| resources.iter().map(|(patient_id, _)| *patient_id)).collect();
+        let patients: Vec<Uuid> = patients.into_iter().collect();
+        let disclosures = self
+            .part2
+            .disclosures(&patients, &Subject::User(actor), PurposeOfUse::Research)
+            .await?;
+        let mut withheld = 0;
+        let mut partitions: Vec<DatasetPartition> = Vec::new();
+        for (spec, resources) in fetched {
+            let total = resources.len();
+            let resources: Vec<Value> = resources
+                .into_iter()
+                .filter(|(patient_id, resource)| {
+                    disclosures
+                        .get(patient_id)
+                        .is_some_and(|disclosure| disclosure.discloses(is_part2_row(resource)))
+                })
+                .map(|(_, resource)| resource)
+                .collect();
+            withheld += total - resources.len();
+            partitions.extend(partition_resources(spec, &resources, query.from, query.to));
+        }
+        let rows: usize = partitions.iter().map(|partition| partition.rows.len()).sum();
+        tracing::info!(
+            "User {} exported {} resources in {} dataset partitions, {} Part 2 records withheld",
+            actor,
+            rows,
+            partitions.len(),
+            withheld
+        );
+
+        let period = match (query.from, query.to) {
+            (None, None) => "all".to_string(),
+            (from, to) => format!(
+                "{}-{}",
+                from.map(|day| day.format("%Y%m%d").to_string()).unwrap_or_default(),
+                to.map(|day| day.format("%Y%m%d").to_string()).unwrap_or_default()
+            ),
+        };
+        Ok(DatasetExport {
+            file_name: format!("fhir-dataset-{}.zip", period),
+            bytes: Self::write(&partitions)?,
+        })
+    }
+
+    #[cfg(feature = "parquet")]
+    fn write(partitions: &[DatasetPartition]) -> Result<Vec<u8>, HimsError> {
+        crate::exporters::columnar::parquet_dataset(partitions)
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn write(_partitions: &[DatasetPartition]) -> Result<Vec<u8>, HimsError> {
+        Err(Self::parquet_unavailable())
+    }
+
+    fn parquet_unavailable() -> HimsError {
+        HimsError::ConfigurationError {
+            message: "Cannot write Parquet datasets: built without the parquet feature".to_string(),
+        }
+    }
+
+    /// Every resource of a type as served, with its patient, paging
+    /// through its search.
+    /// Searches by date take a day either side, as they compare in the
+    /// database's time zone; partitioning keeps to the period in UTC.
+    async fn fetch(&self, resource_type: &str, query: &DatasetQuery) -> Result<Vec<(Uuid, Value)>, HimsError> {
+        let mut dates: Vec<DateParam> = Vec::new();
+        if let Some(from) = query.from {
+            dates.push(DateParam::parse(&format!("ge{}", from - Duration::days(1)))?);
+        }
+        if let Some(to) = query.to {
+            dates.push(DateParam::parse(&format!("le{}", to + Duration::days(1)))?);
+        }
+
+        let mut resources = Vec::new();
+        match resource_type {
+            "Patient" => {
+                let mut search = PatientSearch { active: Some(true), ..Default::default() };
+                search.page.apply("_count", PAGE_SIZE)?;
+                search.page.apply("_total", "none")?;
+                loop {
+                    let page = self
+                        .patients
+                        .search_patients(&search)
+                        .await
+                        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+                    for patient in page.patients {
+                        resources.push((patient.id, to_value(PatientController::patient_to_response(patient))?));
+                    }
+                    match page.next {
+                        Some(next) => search.page.cursor = Some(next),
+                        None => break,
+                    }
+                }
+            }
+            "Condition" => {
+                let mut search = ConditionSearch::default();
+                search.page.apply("_count", PAGE_SIZE)?;
+                search.page.apply("_total", "none")?;
+                loop {
+                    let page = self.conditions.search(&search).await?;
+                    for condition in page.conditions {
+                        resources.push((condition.patient_id, to_value(ConditionController::condition_to_response(condition))?));
+                    }
+                    match page.next {
+                        Some(next) => search.page.cursor = Some(next),
+                        None => break,
+                    }
+                }
+            }
+            "Observation" => {
+                let mut search = VitalSignSearch { date: dates, ..Default::default() };
+                search.page.apply("_count", PAGE_SIZE)?;
+                search.page.apply("_total", "none")?;
+                loop {
+                    let page = self.vital_signs.search(&search).await?;
+                    for sign in page.vital_signs {
+                        resources.push((sign.patient_id, to_value(VitalSignController::vital_sign_to_response(sign))?));
+                    }
+                    match page.next {
+                        Some(next) => search.page.cursor = Some(next),
+                        None => break,
+                    }
+                }
+            }
+            _ => return Err(validation_error(format!("Datasets cannot hold {}", resource_type))),
+        }
+        Ok(resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dataset_query() {
+        let query = DatasetQuery::default();
+        assert_eq!(query.specs().unwrap().len(), DATASET_RESOURCES.len());
+
+        let query = DatasetQuery {
+            resource_types: Some("Observation, Patient,Observation".to_string()),
+            ..Default::default()
+        };
+        let types: Vec<&str> = query.specs().unwrap().iter().map(|spec| spec.resource_type).collect();
+        assert_eq!(types, ["Observation", "Patient"]);
+
+        let query = DatasetQuery { resource_types: Some("Claim".to_string()), ..Default::default() };
+        assert!(query.specs().unwrap_err().contains("Claim"));
+
+        let query = DatasetQuery {
+            from: NaiveDate::from_ymd_opt(2024, 3, 2),
+            to: NaiveDate::from_ymd_opt(2024, 3, 1),
+            ..Default::default()
+        };
+        assert!(query.specs().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! Dataset Module
+//!
+//! This module exports columnar datasets for data scientists including:
+//! - Patients, conditions and observations flattened into typed columns
+//! - Arrow record batches written as Parquet files (behind the parquet feature)
+//! - Files partitioned by resource type and date, Hive-style
+//! - 42 CFR Part 2 records left out without the patient's consent to research
+
+#[path = "dataset.controller.rs"]
+pub mod dataset_controller;
+#[path = "dataset.service.rs"]
+pub mod dataset_service;
+
+pub use dataset_controller::DatasetController;
+pub use dataset_service::{DatasetExport, DatasetQuery, DatasetService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Dataset Module Configuration
+pub struct DatasetModule {
+    pub service: Arc<DatasetService>,
+    pub controller: Arc<DatasetController>,
+}
+
+impl DatasetModule {
+    /// Create a new Dataset Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(DatasetService::new(db_pool));
+        let controller = Arc::new(DatasetController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<DatasetService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,185 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::delegation::delegation_service::{Delegation, DelegationGrant};
+use crate::modules::delegation::DelegationService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Delegation controller for proxy and guardian access workflows
+pub struct DelegationController {
+    delegation_service: Arc<DelegationService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DelegationQuery {
+    pub patient: Option<Uuid>,
+    pub proxy: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeDelegationRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransitionResponse {
+    pub ended: usize,
+    pub delegations: Vec<Delegation>,
+}
+
+impl DelegationController {
+    /// Create new controller with injected service
+    pub fn new(delegation_service: Arc<DelegationService>) -> Self {
+        Self { delegation_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::grant_delegation, "Grant proxy or guardian access")
+            .get("/", Self::search_delegations, "Search active delegations by patient or proxy")
+            .get("/:id", Self::get_delegation, "Get delegation by ID")
+            .post("/:id/revoke", Self::revoke_delegation, "Revoke a delegation")
+            .post("/transitions", Self::process_transitions, "Run age-of-majority and expiry transitions")
+            .with_state(self.delegation_service.clone())
+    }
+
+    /// Grant proxy or guardian access
+    pub async fn grant_delegation(
+        State(delegation_service): State<Arc<DelegationService>>,
+        headers: HeaderMap,
+        Json(payload): Json<DelegationGrant>,
+    ) -> Result<(StatusCode, Json<Delegation>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Granting {} on patient {}", payload.relation, payload.patient_id);
+
+        match delegation_service.grant(actor, payload).await {
+            Ok(delegation) => Ok((StatusCode::CREATED, Json(delegation))),
+            Err(e) => Err(Self::error_response("Failed to grant delegation", e)),
+        }
+    }
+
+    /// Search active delegations by patient or proxy
+    pub async fn search_delegations(
+        State(delegation_service): State<Arc<DelegationService>>,
+        Query(params): Query<DelegationQuery>,
+    ) -> Result<Json<Vec<Delegation>>, (StatusCode, Json<ErrorResponse>)> {
+        let result = match (params.patient, params.proxy) {
+            (Some(patient_id), _) => delegation_service.get_delegations_for_patient(patient_id).await,
+            (None, Some(proxy_user_id)) => delegation_service.get_delegations_for_proxy(proxy_user_id).await,
+            (None, None) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "Invalid search".to_string(),
+                        message: "Either patient or proxy must be provided".to_string(),
+                    }),
+                ));
+            }
+        };
+
+        match result {
+            Ok(delegations) => Ok(Json(delegations)),
+            Err(e) => Err(Self::error_response("Failed to search delegations", e)),
+        }
+    }
+
+    /// Get delegation by ID
+    pub async fn get_delegation(
+        State(delegation_service): State<Arc<DelegationService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Delegation>, (StatusCode, Json<ErrorResponse>)> {
+        match delegation_service.get_delegation(id).await {
+            Ok(Some(delegation)) => Ok(Json(delegation)),
+            Ok(None) => {
+                tracing::warn!("Delegation not found: {}", id);
+                Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Delegation not found".to_string(),
+                        message: format!("Delegation with id {} not found", id),
+                    }),
+                ))
+            }
+            Err(e) => Err(Self::error_response("Failed to retrieve delegation", e)),
+        }
+    }
+
+    /// Revoke a delegation
+    pub async fn revoke_delegation(
+        State(delegation_service): State<Arc<DelegationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RevokeDelegationRequest>,
+    ) -> Result<Json<Delegation>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Revoking delegation: {}", id);
+
+        match delegation_service.revoke(actor, id, payload.reason).await {
+            Ok(delegation) => Ok(Json(delegation)),
+            Err(e) => Err(Self::error_response("Failed to revoke delegation", e)),
+        }
+    }
+
+    /// Run age-of-majority and expiry transitions
+    pub async fn process_transitions(
+        State(delegation_service): State<Arc<DelegationService>>,
+    ) -> Result<Json<TransitionResponse>, (StatusCode, Json<ErrorResponse>)> {
+        match delegation_service.process_transitions().await {
+            Ok(delegations) => {
+                tracing::info!("Ended {} delegations", delegations.len());
+                Ok(Json(TransitionResponse {
+                    ended: delegations.len(),
+                    delegations,
+                }))
+            }
+            Err(e) => Err(Self::error_response("Failed to process delegation transitions", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
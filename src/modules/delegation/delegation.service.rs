@@ -0,0 +1,566 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::{
+    Action, AuthorizationStorage, HealthcareRelation, PostgresAuthorizationStorage, RelationshipTuple,
+    Resource, Subject,
+};
+use crate::modules::role::RoleService;
+
+// Import SQL queries from separate file
+use crate::modules::delegation::delegation_sql::*;
+
+/// Actions a proxy or guardian may be granted on a patient's record.
+/// Clinical actions such as prescribing are never delegable.
+pub const DELEGABLE_ACTIONS: &[Action] = &[
+    Action::Read,
+    Action::Update,
+    Action::ViewResults,
+    Action::Schedule,
+    Action::Cancel,
+    Action::ViewBilling,
+    Action::ProcessPayment,
+];
+
+/// Delegation workflow configuration
+#[derive(Debug, Clone)]
+pub struct DelegationConfig {
+    /// Age at which parental proxy access is revoked automatically
+    pub age_of_majority: i32,
+    /// Maximum lifetime of a non-parental delegation
+    pub max_duration_days: i64,
+}
+
+impl Default for DelegationConfig {
+    fn default() -> Self {
+        Self {
+            age_of_majority: 18,
+            max_duration_days: 365,
+        }
+    }
+}
+
+/// How the proxy's identity and authority were established
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityProofingMethod {
+    InPerson,
+    GovernmentId,
+    BirthCertificate,
+    CourtOrder,
+    PowerOfAttorney,
+    RemoteVerification,
+}
+
+/// Identity proofing evidence recorded with a delegation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityProofing {
+    pub method: IdentityProofingMethod,
+    pub document_type: Option<String>,
+    pub document_reference: Option<String>,
+    pub verified_by: Uuid,
+    pub verified_at: DateTime<Utc>,
+    pub notes: Option<String>,
+}
+
+/// Delegation lifecycle status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationStatus {
+    Active,
+    Revoked,
+    Expired,
+    RevokedAtMajority,
+}
+
+impl DelegationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DelegationStatus::Active => "active",
+            DelegationStatus::Revoked => "revoked",
+            DelegationStatus::Expired => "expired",
+            DelegationStatus::RevokedAtMajority => "revoked_at_majority",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "revoked" => DelegationStatus::Revoked,
+            "expired" => DelegationStatus::Expired,
+            "revoked_at_majority" => DelegationStatus::RevokedAtMajority,
+            _ => DelegationStatus::Active,
+        }
+    }
+}
+
+/// Proxy or guardian access to a patient's record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delegation {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub proxy_user_id: Uuid,
+    pub relation: HealthcareRelation,
+    pub scope: Vec<Action>,
+    pub is_parental: bool,
+    pub identity_proofing: IdentityProofing,
+    pub status: DelegationStatus,
+    pub granted_by: Uuid,
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_by: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revocation_reason: Option<String>,
+}
+
+impl Delegation {
+    /// Relationship tuple mirroring this delegation
+    fn to_tuple(&self) -> RelationshipTuple {
+        let mut metadata = HashMap::new();
+        metadata.insert("delegation_id".to_string(), self.id.to_string());
+        metadata.insert(
+            "scope".to_string(),
+            self.scope.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(","),
+        );
+
+        RelationshipTuple {
+            object: Resource::Patient(self.patient_id),
+            relation: self.relation.clone(),
+            subject: Subject::User(self.proxy_user_id),
+            context: Some("delegation".to_string()),
+            expires_at: self.expires_at,
+            created_by: Some(self.granted_by),
+            created_at: self.granted_at,
+            metadata,
+        }
+    }
+}
+
+/// Request to delegate access to a patient's record
+#[derive(Debug, Clone, Deserialize)]
+pub struct DelegationGrant {
+    pub patient_id: Uuid,
+    pub proxy_user_id: Uuid,
+    /// "proxy_access" or "guardian"
+    pub relation: String,
+    /// Action names within `DELEGABLE_ACTIONS`
+    pub scope: Vec<String>,
+    #[serde(default)]
+    pub is_parental: bool,
+    pub identity_proofing: IdentityProofing,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Service for proxy and guardian access workflows
+pub struct DelegationService {
+    pool: PgPool,
+    storage: PostgresAuthorizationStorage,
+    role_service: RoleService,
+    config: DelegationConfig,
+}
+
+impl DelegationService {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_config(pool, DelegationConfig::default())
+    }
+
+    pub fn with_config(pool: PgPool, config: DelegationConfig) -> Self {
+        Self {
+            storage: PostgresAuthorizationStorage::new(pool.clone()),
+            role_service: RoleService::new(pool.clone()),
+            pool,
+            config,
+        }
+    }
+
+    /// Grant a proxy or guardian access to a patient's record
+    pub async fn grant(&self, actor: Uuid, grant: DelegationGrant) -> Result<Delegation, HimsError> {
+        let relation = Self::parse_relation(&grant.relation)?;
+        let scope = Self::validate_scope(&grant.scope)?;
+
+        if grant.proxy_user_id == actor {
+            return Err(HimsError::SecurityError {
+                message: "Users cannot delegate access to themselves".to_string(),
+            });
+        }
+
+        // Patients may delegate their own record; parental access is set up
+        // by administrators after verifying the relationship.
+        let is_admin = self.is_admin(actor).await?;
+        if grant.is_parental && !is_admin {
+            return Err(HimsError::SecurityError {
+                message: "Parental delegations must be granted by an administrator".to_string(),
+            });
+        }
+        if !is_admin && !self.is_patient_self(actor, grant.patient_id).await? {
+            return Err(HimsError::SecurityError {
+                message: "Only the patient or an administrator can delegate access".to_string(),
+            });
+        }
+
+        let now = Utc::now();
+        let expires_at = if grant.is_parental {
+            let birth_date = self.get_patient_birth_date(grant.patient_id).await?;
+            if Self::has_reached_majority(birth_date, now.date_naive(), self.config.age_of_majority) {
+                return Err(HimsError::ValidationError {
+                    message: "Patient has reached the age of majority; parental proxy is not permitted".to_string(),
+                });
+            }
+            grant.expires_at
+        } else {
+            let max_expiry = now + Duration::days(self.config.max_duration_days);
+            match grant.expires_at {
+                Some(expires_at) if expires_at <= now => {
+                    return Err(HimsError::ValidationError {
+                        message: "Delegation expiry must be in the future".to_string(),
+                    });
+                }
+                Some(expires_at) if expires_at > max_expiry => {
+                    return Err(HimsError::ValidationError {
+                        message: format!(
+                            "Delegation cannot exceed {} days",
+                            self.config.max_duration_days
+                        ),
+                    });
+                }
+                Some(expires_at) => Some(expires_at),
+                None => Some(max_expiry),
+            }
+        };
+
+        let delegation = Delegation {
+            id: Uuid::new_v4(),
+            patient_id: grant.patient_id,
+            proxy_user_id: grant.proxy_user_id,
+            relation,
+            scope,
+            is_parental: grant.is_parental,
+            identity_proofing: grant.identity_proofing,
+            status: DelegationStatus::Active,
+            granted_by: actor,
+            granted_at: now,
+            expires_at,
+            revoked_by: None,
+            revoked_at: None,
+            revocation_reason: None,
+        };
+
+        sqlx::query(INSERT_DELEGATION)
+            .bind(delegation.id)
+            .bind(delegation.patient_id)
+            .bind(delegation.proxy_user_id)
+            .bind(delegation.relation.to_string())
+            .bind(Self::scope_to_json(&delegation.scope))
+            .bind(delegation.is_parental)
+            .bind(serde_json::to_value(&delegation.identity_proofing).unwrap_or_default())
+            .bind(delegation.granted_by)
+            .bind(delegation.granted_at)
+            .bind(delegation.expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.storage.store_relationship(&delegation.to_tuple()).await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!(
+            "Delegation {} granted: {} on patient {} to {}",
+            delegation.id, delegation.relation, delegation.patient_id, delegation.proxy_user_id
+        );
+
+        Ok(delegation)
+    }
+
+    /// Get delegation by ID
+    pub async fn get_delegation(&self, id: Uuid) -> Result<Option<Delegation>, HimsError> {
+        let row = sqlx::query(GET_DELEGATION_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.map(|row| Self::row_to_delegation(&row)))
+    }
+
+    /// Active delegations on a patient's record
+    pub async fn get_delegations_for_patient(&self, patient_id: Uuid) -> Result<Vec<Delegation>, HimsError> {
+        let rows = sqlx::query(GET_DELEGATIONS_FOR_PATIENT)
+            .bind(patient_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_delegation).collect())
+    }
+
+    /// Active delegations held by a proxy
+    pub async fn get_delegations_for_proxy(&self, proxy_user_id: Uuid) -> Result<Vec<Delegation>, HimsError> {
+        let rows = sqlx::query(GET_DELEGATIONS_FOR_PROXY)
+            .bind(proxy_user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_delegation).collect())
+    }
+
+    /// Revoke a delegation. The patient, the proxy or an administrator may revoke.
+    pub async fn revoke(&self, actor: Uuid, id: Uuid, reason: Option<String>) -> Result<Delegation, HimsError> {
+        let delegation = self.get_delegation(id).await?.ok_or_else(|| HimsError::ValidationError {
+            message: format!("Delegation with id {} not found", id),
+        })?;
+
+        let permitted = actor == delegation.proxy_user_id
+            || self.is_admin(actor).await?
+            || (!delegation.is_parental && self.is_patient_self(actor, delegation.patient_id).await?);
+        if !permitted {
+            return Err(HimsError::SecurityError {
+                message: "Not permitted to revoke this delegation".to_string(),
+            });
+        }
+
+        self.end_delegation(delegation, DelegationStatus::Revoked, Some(actor), reason).await
+    }
+
+    /// Whether a proxy may perform an action on a patient's record
+    pub async fn permits(&self, proxy_user_id: Uuid, patient_id: Uuid, action: &Action) -> Result<bool, HimsError> {
+        let rows = sqlx::query(GET_ACTIVE_DELEGATION)
+            .bind(patient_id)
+            .bind(proxy_user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(Self::row_to_delegation)
+            .any(|delegation| delegation.scope.contains(action)))
+    }
+
+    /// Revoke parental delegations for patients who reached the age of majority
+    /// and close out delegations past their expiry
+    pub async fn process_transitions(&self) -> Result<Vec<Delegation>, HimsError> {
+        let mut ended = Vec::new();
+
+        let rows = sqlx::query(GET_PARENTAL_DELEGATIONS_AT_MAJORITY)
+            .bind(self.config.age_of_majority)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        for delegation in rows.iter().map(Self::row_to_delegation) {
+            tracing::info!(
+                "Revoking parental delegation {}: patient {} reached age {}",
+                delegation.id, delegation.patient_id, self.config.age_of_majority
+            );
+            ended.push(
+                self.end_delegation(
+                    delegation,
+                    DelegationStatus::RevokedAtMajority,
+                    None,
+                    Some("Patient reached the age of majority".to_string()),
+                ).await?,
+            );
+        }
+
+        let rows = sqlx::query(GET_EXPIRED_DELEGATIONS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        for delegation in rows.iter().map(Self::row_to_delegation) {
+            ended.push(self.end_delegation(delegation, DelegationStatus::Expired, None, None).await?);
+        }
+
+        Ok(ended)
+    }
+
+    /// Mark a delegation inactive and remove its relationship tuple
+    async fn end_delegation(
+        &self,
+        mut delegation: Delegation,
+        status: DelegationStatus,
+        actor: Option<Uuid>,
+        reason: Option<String>,
+    ) -> Result<Delegation, HimsError> {
+        let now = Utc::now();
+
+        sqlx::query(REVOKE_DELEGATION)
+            .bind(delegation.id)
+            .bind(status.as_str())
+            .bind(actor)
+            .bind(now)
+            .bind(reason.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.storage.remove_relationship(&delegation.to_tuple()).await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Delegation {} ended with status {}", delegation.id, status.as_str());
+
+        delegation.status = status;
+        delegation.revoked_by = actor;
+        delegation.revoked_at = Some(now);
+        delegation.revocation_reason = reason;
+        Ok(delegation)
+    }
+
+    async fn is_admin(&self, actor: Uuid) -> Result<bool, HimsError> {
+        Ok(self.role_service.get_user_permissions(actor).await?.contains(&Action::ManageUsers))
+    }
+
+    /// Whether the actor is the patient whose record is being delegated
+    async fn is_patient_self(&self, actor: Uuid, patient_id: Uuid) -> Result<bool, HimsError> {
+        self.storage
+            .has_relationship(
+                &Resource::Patient(patient_id),
+                &HealthcareRelation::DataSubject,
+                &Subject::User(actor),
+            )
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))
+    }
+
+    async fn get_patient_birth_date(&self, patient_id: Uuid) -> Result<Option<NaiveDate>, HimsError> {
+        let row = sqlx::query(GET_PATIENT_BIRTH_DATE)
+            .bind(patient_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| HimsError::ValidationError {
+                message: format!("Patient with id {} not found", patient_id),
+            })?;
+
+        Ok(row.get("birth_date"))
+    }
+
+    /// Whether someone born on `birth_date` is at least `age` years old on `today`.
+    /// An unknown birth date is treated as not having reached majority.
+    pub fn has_reached_majority(birth_date: Option<NaiveDate>, today: NaiveDate, age: i32) -> bool {
+        use chrono::Datelike;
+
+        let Some(birth_date) = birth_date else {
+            return false;
+        };
+
+        let mut years = today.year() - birth_date.year();
+        if (today.month(), today.day()) < (birth_date.month(), birth_date.day()) {
+            years -= 1;
+        }
+        years >= age
+    }
+
+    fn parse_relation(relation: &str) -> Result<HealthcareRelation, HimsError> {
+        match HealthcareRelation::from_str(relation) {
+            Ok(HealthcareRelation::ProxyAccess) => Ok(HealthcareRelation::ProxyAccess),
+            Ok(HealthcareRelation::Guardian) => Ok(HealthcareRelation::Guardian),
+            _ => Err(HimsError::ValidationError {
+                message: format!("Relation '{}' cannot be delegated; use proxy_access or guardian", relation),
+            }),
+        }
+    }
+
+    /// Parse and restrict the requested scope to delegable actions
+    pub fn validate_scope(scope: &[String]) -> Result<Vec<Action>, HimsError> {
+        if scope.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Delegation scope must include at least one action".to_string(),
+            });
+        }
+
+        let mut actions = Vec::new();
+        for name in scope {
+            let action = Action::from_str(name).unwrap_or_else(|_| Action::Custom(name.clone()));
+            if !DELEGABLE_ACTIONS.contains(&action) {
+                return Err(HimsError::ValidationError {
+                    message: format!("Action '{}' cannot be delegated", name),
+                });
+            }
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+        Ok(actions)
+    }
+
+    fn scope_to_json(scope: &[Action]) -> serde_json::Value {
+        serde_json::Value::Array(
+            scope.iter().map(|a| serde_json::Value::String(a.to_string())).collect(),
+        )
+    }
+
+    fn row_to_delegation(row: &sqlx::postgres::PgRow) -> Delegation {
+        let scope: serde_json::Value = row.get("scope");
+        let scope = scope
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .filter_map(|s| Action::from_str(s).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let relation: String = row.get("relation");
+        let identity_proofing: serde_json::Value = row.get("identity_proofing");
+        let granted_by: Uuid = row.get("granted_by");
+        let granted_at: DateTime<Utc> = row.get("granted_at");
+
+        Delegation {
+            id: row.get("id"),
+            patient_id: row.get("patient_id"),
+            proxy_user_id: row.get("proxy_user_id"),
+            relation: HealthcareRelation::from_str(&relation)
+                .unwrap_or(HealthcareRelation::ProxyAccess),
+            scope,
+            is_parental: row.get("is_parental"),
+            identity_proofing: serde_json::from_value(identity_proofing).unwrap_or(IdentityProofing {
+                method: IdentityProofingMethod::InPerson,
+                document_type: None,
+                document_reference: None,
+                verified_by: granted_by,
+                verified_at: granted_at,
+                notes: None,
+            }),
+            status: DelegationStatus::from_string(&row.get::<String, _>("status")),
+            granted_by,
+            granted_at,
+            expires_at: row.get("expires_at"),
+            revoked_by: row.get("revoked_by"),
+            revoked_at: row.get("revoked_at"),
+            revocation_reason: row.get("revocation_reason"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_majority_respects_birthday() {
+        let birth = NaiveDate::from_ymd_opt(2008, 6, 15);
+        let before = NaiveDate::from_ymd_opt(2026, 6, 14).unwrap();
+        let on = NaiveDate::from_ymd_opt(2026, 6, 15).unwrap();
+
+        assert!(!DelegationService::has_reached_majority(birth, before, 18));
+        assert!(DelegationService::has_reached_majority(birth, on, 18));
+        assert!(!DelegationService::has_reached_majority(None, on, 18));
+    }
+
+    #[test]
+    fn test_scope_rejects_clinical_actions() {
+        let scope = DelegationService::validate_scope(&["read".to_string(), "schedule".to_string()]).unwrap();
+        assert_eq!(scope, vec![Action::Read, Action::Schedule]);
+
+        assert!(DelegationService::validate_scope(&["prescribe".to_string()]).is_err());
+        assert!(DelegationService::validate_scope(&[]).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! Delegation SQL Queries
+//! 
+//! This file contains all SQL queries used by the delegation service
+//! for clean separation of concerns and better maintainability.
+
+/// Insert a new delegation
+pub const INSERT_DELEGATION: &str = r#"
+    INSERT INTO patient_delegations (
+        id, patient_id, proxy_user_id, relation, scope, is_parental,
+        identity_proofing, status, granted_by, granted_at, expires_at
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, 'active', $8, $9, $10
+    )
+"#;
+
+/// Get delegation by ID
+pub const GET_DELEGATION_BY_ID: &str = r#"
+    SELECT id, patient_id, proxy_user_id, relation, scope, is_parental, identity_proofing,
+           status, granted_by, granted_at, expires_at, revoked_by, revoked_at, revocation_reason
+    FROM patient_delegations
+    WHERE id = $1
+"#;
+
+/// Get active delegations for a patient
+pub const GET_DELEGATIONS_FOR_PATIENT: &str = r#"
+    SELECT id, patient_id, proxy_user_id, relation, scope, is_parental, identity_proofing,
+           status, granted_by, granted_at, expires_at, revoked_by, revoked_at, revocation_reason
+    FROM patient_delegations
+    WHERE patient_id = $1 AND status = 'active'
+    ORDER BY granted_at DESC
+"#;
+
+/// Get active delegations held by a proxy
+pub const GET_DELEGATIONS_FOR_PROXY: &str = r#"
+    SELECT id, patient_id, proxy_user_id, relation, scope, is_parental, identity_proofing,
+           status, granted_by, granted_at, expires_at, revoked_by, revoked_at, revocation_reason
+    FROM patient_delegations
+    WHERE proxy_user_id = $1 AND status = 'active'
+    ORDER BY granted_at DESC
+"#;
+
+/// Get the active, unexpired delegation between a proxy and a patient
+pub const GET_ACTIVE_DELEGATION: &str = r#"
+    SELECT id, patient_id, proxy_user_id, relation, scope, is_parental, identity_proofing,
+           status, granted_by, granted_at, expires_at, revoked_by, revoked_at, revocation_reason
+    FROM patient_delegations
+    WHERE patient_id = $1 AND proxy_user_id = $2 AND status = 'active'
+      AND (expires_at IS NULL OR expires_at > NOW())
+"#;
+
+/// Mark a delegation as no longer active
+pub const REVOKE_DELEGATION: &str = r#"
+    UPDATE patient_delegations
+    SET status = $2, revoked_by = $3, revoked_at = $4, revocation_reason = $5
+    WHERE id = $1 AND status = 'active'
+"#;
+
+/// Parental delegations whose patient has reached the age of majority
+pub const GET_PARENTAL_DELEGATIONS_AT_MAJORITY: &str = r#"
+    SELECT d.id, d.patient_id, d.proxy_user_id, d.relation, d.scope, d.is_parental, d.identity_proofing,
+           d.status, d.granted_by, d.granted_at, d.expires_at, d.revoked_by, d.revoked_at, d.revocation_reason
+    FROM patient_delegations d
+    JOIN patients p ON p.id = d.patient_id
+    WHERE d.is_parental = true
+      AND d.status = 'active'
+      AND p.birth_date IS NOT NULL
+      AND p.birth_date <= (CURRENT_DATE - make_interval(years => $1))
+"#;
+
+/// Delegations past their expiry that are still marked active
+pub const GET_EXPIRED_DELEGATIONS: &str = r#"
+    SELECT id, patient_id, proxy_user_id, relation, scope, is_parental, identity_proofing,
+           status, granted_by, granted_at, expires_at, revoked_by, revoked_at, revocation_reason
+    FROM patient_delegations
+    WHERE status = 'active' AND expires_at IS NOT NULL AND expires_at <= NOW()
+"#;
+
+/// Get a patient's birth date
+pub const GET_PATIENT_BIRTH_DATE: &str = r#"
+    SELECT birth_date FROM patients WHERE id = $1
+"#;
@@ -0,0 +1,51 @@
+//! Delegation Module
+//! 
+//! This module provides proxy and guardian access workflows including:
+//! - ProxyAccess/Guardian grants with scope limits and expiry
+//! - Identity proofing evidence for each delegation
+//! - Revocation by patient, proxy or administrator
+//! - Automatic revocation of parental proxy at the age of majority
+
+#[path = "delegation.controller.rs"]
+pub mod delegation_controller;
+#[path = "delegation.service.rs"]
+pub mod delegation_service;
+#[path = "delegation.sql.rs"]
+pub mod delegation_sql;
+
+pub use delegation_controller::DelegationController;
+pub use delegation_service::{DelegationConfig, DelegationService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Delegation Module Configuration
+pub struct DelegationModule {
+    pub service: Arc<DelegationService>,
+    pub controller: Arc<DelegationController>,
+}
+
+impl DelegationModule {
+    /// Create a new Delegation Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(DelegationService::new(db_pool));
+        let controller = Arc::new(DelegationController::new(service.clone()));
+        
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<DelegationService> {
+        self.service.clone()
+    }
+}
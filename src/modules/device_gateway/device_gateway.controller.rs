@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Extension, State},
+    http::{header, HeaderName, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::core::HimsError;
+use crate::modules::authorization::Action;
+use crate::modules::device_gateway::device_gateway_mapping::{parse_oru, DeviceReadingsRequest};
+use crate::modules::device_gateway::device_gateway_service::IngestReport;
+use crate::modules::device_gateway::DeviceGatewayService;
+use crate::modules::service_account::ServicePrincipal;
+use crate::standards::hl7v2::generator::Hl7Generator;
+use crate::utils::api_router::ApiRouter;
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Device gateway controller receiving bedside device readings
+pub struct DeviceGatewayController {
+    device_gateway_service: Arc<DeviceGatewayService>,
+}
+
+impl DeviceGatewayController {
+    /// Create new controller with injected service
+    pub fn new(device_gateway_service: Arc<DeviceGatewayService>) -> Self {
+        Self { device_gateway_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/readings", Self::receive_readings, "Receive device readings as IEEE 11073 observations")
+            .post("/hl7", Self::receive_hl7, "Receive device readings as an HL7 v2 ORU^R01 message")
+            .with_state(self.device_gateway_service.clone())
+    }
+
+    /// Receive a device's readings in the JSON mapping of IEEE 11073
+    /// observations, reporting what became of each
+    pub async fn receive_readings(
+        State(service): State<Arc<DeviceGatewayService>>,
+        principal: Option<Extension<ServicePrincipal>>,
+        Json(request): Json<DeviceReadingsRequest>,
+    ) -> Result<Json<IngestReport>, ErrorReply> {
+        Self::require_device(principal)?;
+        let batch = request.into_batch().map_err(|message| {
+            Self::error_response("Invalid device readings", HimsError::ValidationError { message })
+        })?;
+
+        match service.ingest(batch).await {
+            Ok(report) => Ok(Json(report)),
+            Err(e) => Err(Self::error_response("Failed to record device readings", e)),
+        }
+    }
+
+    /// Receive a device's readings as an HL7 v2 ORU^R01 message and
+    /// acknowledge it: AA once every reading is recorded or debounced, AE
+    /// when the patient is unknown or a reading is rejected, e.g. for an
+    /// implausible value, and AR when the message cannot be read
+    pub async fn receive_hl7(
+        State(service): State<Arc<DeviceGatewayService>>,
+        principal: Option<Extension<ServicePrincipal>>,
+        body: String,
+    ) -> Result<([(HeaderName, &'static str); 1], String), ErrorReply> {
+        Self::require_device(principal)?;
+        let (control_id, ack_code) = match parse_oru(&body) {
+            Ok((control_id, batch)) => match service.ingest(batch).await {
+                Ok(report) if report.rejected.is_empty() => (control_id, "AA"),
+                Ok(report) => {
+                    tracing::warn!("Rejected device readings in {}: {}", control_id, report.rejected.join("; "));
+                    (control_id, "AE")
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to record device readings {}: {}", control_id, e);
+                    (control_id, "AE")
+                }
+            },
+            Err(message) => {
+                tracing::warn!("Rejected device message: {}", message);
+                (String::new(), "AR")
+            }
+        };
+
+        match Hl7Generator::generate_ack_message(&control_id, ack_code) {
+            Ok(ack) => Ok(([(header::CONTENT_TYPE, "application/hl7-v2")], ack)),
+            Err(e) => Err(Self::error_response("Failed to acknowledge device readings", e)),
+        }
+    }
+
+    /// Readings are only taken from a device's own API key, and only when
+    /// the key is scoped to record device data
+    fn require_device(principal: Option<Extension<ServicePrincipal>>) -> Result<(), ErrorReply> {
+        let message = match principal {
+            Some(Extension(principal)) if principal.allows(&Action::RecordDeviceData) => return Ok(()),
+            Some(Extension(principal)) => {
+                tracing::warn!("Service account {} may not record device data", principal.account_name);
+                format!("Missing required permission: {}", Action::RecordDeviceData)
+            }
+            None => "Device readings need a device API key".to_string(),
+        };
+        Err(Self::error_response(
+            "Device readings refused",
+            HimsError::SecurityError { message },
+        ))
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
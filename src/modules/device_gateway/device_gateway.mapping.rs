@@ -0,0 +1,489 @@
+//! Readings of bedside devices, from HL7 v2 ORU^R01 results as IHE PCD-01
+//! sends them or a simplified JSON mapping of IEEE 11073 observations
+//!
+//! Observations are named by their IEEE 11073-10101 (MDC) term, or for HL7
+//! by LOINC, and measured in MDC or UCUM units; both are mapped to the
+//! vital signs and UCUM units the vital-signs profiles use. Observations of
+//! anything else a monitor reports, such as mean pressure or ST segments,
+//! are counted and left out.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::models::{DeviceSource, ObservationStatus, Quantity, VitalSignComponent, VitalSignKind};
+use crate::modules::vital_sign::vital_sign_units::ucum;
+use crate::modules::vital_sign::VitalSignRequest;
+use crate::standards::hl7v2::parser::{Hl7Parser, Hl7Segment};
+
+/// MDC terms of vital signs: numeric code, reference ID and the sign
+const MDC_TERMS: &[(u32, &str, VitalSignKind)] = &[
+    (147842, "MDC_ECG_HEART_RATE", VitalSignKind::HeartRate),
+    (149530, "MDC_PULS_OXIM_PULS_RATE", VitalSignKind::HeartRate),
+    (149546, "MDC_PULS_RATE_NON_INV", VitalSignKind::HeartRate),
+    (150456, "MDC_PULS_OXIM_SAT_O2", VitalSignKind::OxygenSaturation),
+    (151562, "MDC_RESP_RATE", VitalSignKind::RespiratoryRate),
+    (150021, "MDC_PRESS_BLD_NONINV_SYS", VitalSignKind::SystolicBloodPressure),
+    (150022, "MDC_PRESS_BLD_NONINV_DIA", VitalSignKind::DiastolicBloodPressure),
+    (150364, "MDC_TEMP_BODY", VitalSignKind::BodyTemperature),
+    (188736, "MDC_MASS_BODY_ACTUAL", VitalSignKind::BodyWeight),
+    (188740, "MDC_LEN_BODY_ACTUAL", VitalSignKind::BodyHeight),
+    (188752, "MDC_RATIO_MASS_BODY_LEN_SQ", VitalSignKind::Bmi),
+];
+
+/// MDC units of vital signs: numeric code, reference ID and UCUM code
+const MDC_UNITS: &[(u32, &str, &str)] = &[
+    (262688, "MDC_DIM_PERCENT", "%"),
+    (264864, "MDC_DIM_BEAT_PER_MIN", "/min"),
+    (264928, "MDC_DIM_RESP_PER_MIN", "/min"),
+    (266016, "MDC_DIM_MMHG", "mm[Hg]"),
+    (268192, "MDC_DIM_DEGC", "Cel"),
+    (266560, "MDC_DIM_FAHR", "[degF]"),
+    (263875, "MDC_DIM_KILO_G", "kg"),
+    (263904, "MDC_DIM_LB", "[lb_av]"),
+    (263441, "MDC_DIM_CENTI_M", "cm"),
+    (263520, "MDC_DIM_INCH", "[in_i]"),
+    (264096, "MDC_DIM_KG_PER_M_SQ", "kg/m2"),
+];
+
+/// The vital sign an MDC term, by numeric code or reference ID, measures
+pub fn mdc_kind(term: &str) -> Option<VitalSignKind> {
+    let term = term.trim();
+    MDC_TERMS
+        .iter()
+        .find(|(code, refid, _)| *refid == term || term.parse() == Ok(*code))
+        .map(|(_, _, kind)| *kind)
+}
+
+/// UCUM code of an MDC unit, by numeric code or reference ID
+pub fn mdc_unit(unit: &str) -> Option<&'static str> {
+    let unit = unit.trim();
+    MDC_UNITS
+        .iter()
+        .find(|(code, refid, _)| *refid == unit || unit.parse() == Ok(*code))
+        .map(|(_, _, ucum)| *ucum)
+}
+
+/// Who a device's readings are of
+#[derive(Debug, Clone, PartialEq)]
+pub enum DevicePatient {
+    /// `Patient/{id}` on this server
+    Id(Uuid),
+    /// An identifier (MRN) the device was given for the patient, with the
+    /// system that issued it
+    Identifier { system: String, value: String },
+}
+
+/// A numeric measurement a device reported, in UCUM
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceReading {
+    pub kind: VitalSignKind,
+    pub value: Quantity,
+    pub effective: DateTime<Utc>,
+    pub status: ObservationStatus,
+}
+
+/// One patient's readings from one device, as received
+#[derive(Debug, Clone)]
+pub struct DeviceBatch {
+    pub patient: DevicePatient,
+    pub encounter_id: Option<Uuid>,
+    pub device: DeviceSource,
+    pub readings: Vec<DeviceReading>,
+    /// Readings that could not be read, and why
+    pub rejected: Vec<String>,
+    /// Observations that are not vital signs, or that the device could
+    /// not obtain
+    pub ignored: usize,
+}
+
+impl DeviceBatch {
+    /// The readings as vital signs of `patient_id`, oldest first. Systolic
+    /// and diastolic pressures taken at the same time become one blood
+    /// pressure; a pressure missing its partner is left for validation to
+    /// reject.
+    pub fn requests(&self, patient_id: Uuid) -> Vec<VitalSignRequest> {
+        let request = |kind, effective, status| VitalSignRequest {
+            patient_id,
+            encounter_id: self.encounter_id,
+            status,
+            kind,
+            value: None,
+            component: Vec::new(),
+            effective: Some(effective),
+            device: Some(self.device.clone()),
+            performer: None,
+            note: None,
+        };
+
+        let mut requests = Vec::new();
+        let mut pressures: BTreeMap<DateTime<Utc>, Vec<&DeviceReading>> = BTreeMap::new();
+        for reading in &self.readings {
+            if reading.kind.is_component() {
+                pressures.entry(reading.effective).or_default().push(reading);
+            } else {
+                let mut sign = request(reading.kind, reading.effective, reading.status);
+                sign.value = Some(reading.value.clone());
+                requests.push(sign);
+            }
+        }
+        for (effective, components) in pressures {
+            let mut sign = request(VitalSignKind::BloodPressure, effective, components[0].status);
+            sign.component = components
+                .into_iter()
+                .map(|reading| VitalSignComponent {
+                    kind: reading.kind,
+                    value: reading.value.clone(),
+                })
+                .collect();
+            requests.push(sign);
+        }
+        requests.sort_by_key(|request| request.effective);
+        requests
+    }
+}
+
+fn field(segment: &Hl7Segment, index: usize) -> &str {
+    segment.fields.get(index).map(String::as_str).unwrap_or_default()
+}
+
+fn component(value: &str, index: usize) -> &str {
+    value.split('^').nth(index).unwrap_or_default()
+}
+
+/// HL7 TS (`YYYYMMDD[HHMM[SS[.S]]][+/-ZZZZ]`) as UTC; times without an
+/// offset are taken as UTC
+fn hl7_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+    let local = match digits.len() {
+        8 => NaiveDate::parse_from_str(&digits, "%Y%m%d").ok()?.and_hms_opt(0, 0, 0),
+        12 => NaiveDateTime::parse_from_str(&digits, "%Y%m%d%H%M").ok(),
+        14.. => NaiveDateTime::parse_from_str(&digits[..14], "%Y%m%d%H%M%S").ok(),
+        _ => None,
+    }?;
+    let offset = value.find(['+', '-']).and_then(|start| {
+        let zone = value.get(start + 1..start + 5)?;
+        let minutes = zone[..2].parse::<i32>().ok()? * 60 + zone[2..].parse::<i32>().ok()?;
+        let sign = if value[start..].starts_with('-') { -1 } else { 1 };
+        FixedOffset::east_opt(sign * minutes * 60)
+    });
+    match offset {
+        Some(offset) => offset.from_local_datetime(&local).single().map(|at| at.with_timezone(&Utc)),
+        None => Some(local.and_utc()),
+    }
+}
+
+/// The vital sign an observation identifier (code, text, coding system)
+/// names, by LOINC or MDC
+fn observed_kind(identifier: &str) -> Option<VitalSignKind> {
+    let code = component(identifier, 0);
+    match component(identifier, 2) {
+        "LN" => VitalSignKind::from_loinc_code(code),
+        _ => mdc_kind(code).or_else(|| mdc_kind(component(identifier, 1))),
+    }
+}
+
+/// UCUM code of a unit (code, text, coding system) in MDC or UCUM
+fn observed_unit(unit: &str) -> Option<String> {
+    let code = component(unit, 0);
+    if let Some(ucum) = mdc_unit(code).or_else(|| mdc_unit(component(unit, 1))) {
+        return Some(ucum.to_string());
+    }
+    let system = component(unit, 2);
+    (!code.is_empty() && (system.is_empty() || system.eq_ignore_ascii_case("UCUM"))).then(|| code.to_string())
+}
+
+/// Read an HL7 v2 ORU^R01 unsolicited result from a device: PID-3 and its
+/// assigning authority for the patient, and an OBX per numeric observation, timed by OBX-14, OBR-7 or
+/// MSH-7 and attributed to the device in OBX-18, or failing that the
+/// sending application. OBX segments with no value, such as those naming
+/// the device's channels, are skipped. Returns the message's control ID
+/// (MSH-10) with the readings.
+pub fn parse_oru(message: &str) -> Result<(String, DeviceBatch), String> {
+    let parsed = Hl7Parser::new().parse_message(message).map_err(|e| e.to_string())?;
+    let mut message_type = parsed.message_type.split('^');
+    if (message_type.next(), message_type.next()) != (Some("ORU"), Some("R01")) {
+        return Err(format!("{} is not an unsolicited result (ORU^R01)", parsed.message_type));
+    }
+
+    let mut control_id = String::new();
+    let mut sending_application = String::new();
+    let mut patient_identifier = String::new();
+    let mut patient_identifier_system = String::new();
+    let mut message_time = None;
+    let mut request_time = None;
+    let mut serial_number = None;
+    let mut readings = Vec::new();
+    let mut rejected = Vec::new();
+    let mut ignored = 0;
+
+    for segment in &parsed.segments {
+        match segment.segment_type.as_str() {
+            // MSH fields are numbered from the field separator
+            "MSH" => {
+                sending_application = component(field(segment, 2), 0).to_string();
+                message_time = hl7_timestamp(field(segment, 6));
+                control_id = field(segment, 9).to_string();
+            }
+            // PID-3, first repetition: ID and assigning authority components
+            "PID" => {
+                let identifier = field(segment, 2).split('~').next().unwrap_or_default();
+                patient_identifier = component(identifier, 0).to_string();
+                patient_identifier_system = component(identifier, 3).split('&').next().unwrap_or_default().to_string();
+            }
+            "OBR" => request_time = hl7_timestamp(field(segment, 6)),
+            "OBX" => {
+                let value = field(segment, 4).trim();
+                if value.is_empty() {
+                    continue;
+                }
+                let identifier = field(segment, 2);
+                let Some(kind) = observed_kind(identifier) else {
+                    ignored += 1;
+                    continue;
+                };
+                let status = match component(field(segment, 10), 0) {
+                    "F" => ObservationStatus::Final,
+                    "C" => ObservationStatus::Corrected,
+                    // Not obtained, deleted or wrong
+                    "X" | "D" | "W" => {
+                        ignored += 1;
+                        continue;
+                    }
+                    _ => ObservationStatus::Preliminary,
+                };
+                let name = format!("OBX {} ({})", field(segment, 0), kind.display());
+                if !matches!(field(segment, 1), "" | "NM") {
+                    rejected.push(format!("{}: value type {} is not numeric", name, field(segment, 1)));
+                    continue;
+                }
+                let Ok(number) = value.parse::<f64>() else {
+                    rejected.push(format!("{}: {:?} is not a number", name, value));
+                    continue;
+                };
+                let Some(unit) = observed_unit(field(segment, 5)) else {
+                    rejected.push(format!("{}: no MDC or UCUM unit", name));
+                    continue;
+                };
+                let equipment = component(field(segment, 17).split('~').next().unwrap_or_default(), 0);
+                if serial_number.is_none() && !equipment.is_empty() {
+                    serial_number = Some(equipment.to_string());
+                }
+                let effective = hl7_timestamp(field(segment, 13)).or(request_time).or(message_time);
+                readings.push(DeviceReading {
+                    kind,
+                    value: ucum(number, &unit),
+                    effective: effective.unwrap_or_else(Utc::now),
+                    status,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if patient_identifier.is_empty() {
+        return Err("No patient identifier in PID-3".to_string());
+    }
+    if patient_identifier_system.is_empty() {
+        return Err("No assigning authority for the patient identifier in PID-3".to_string());
+    }
+    let serial_number = serial_number.or((!sending_application.is_empty()).then_some(sending_application));
+    if serial_number.is_none() {
+        return Err("No device in OBX-18 or MSH-3".to_string());
+    }
+    Ok((
+        control_id,
+        DeviceBatch {
+            patient: DevicePatient::Identifier {
+                system: patient_identifier_system,
+                value: patient_identifier,
+            },
+            encounter_id: None,
+            device: DeviceSource {
+                serial_number,
+                ..DeviceSource::default()
+            },
+            readings,
+            rejected,
+            ignored,
+        },
+    ))
+}
+
+/// Readings of one patient from one device, in the JSON mapping of IEEE
+/// 11073 observations
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceReadingsRequest {
+    /// The patient, by ID or by an identifier value and the system that
+    /// issued it
+    pub patient_id: Option<Uuid>,
+    pub patient_identifier: Option<String>,
+    pub patient_identifier_system: Option<String>,
+    pub encounter_id: Option<Uuid>,
+    /// Identified by at least one of its reference, serial number and UDI
+    pub device: DeviceSource,
+    pub readings: Vec<DeviceObservation>,
+}
+
+/// An IEEE 11073 numeric observation
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceObservation {
+    /// MDC reference ID, such as `MDC_PULS_OXIM_SAT_O2`, or numeric code
+    pub code: String,
+    pub value: f64,
+    /// MDC unit reference ID or numeric code, or a UCUM code
+    pub unit: String,
+    /// When the device took the measurement
+    pub time: DateTime<Utc>,
+    /// Final unless the device says otherwise
+    #[serde(default)]
+    pub status: ObservationStatus,
+}
+
+impl DeviceReadingsRequest {
+    pub fn into_batch(self) -> Result<DeviceBatch, String> {
+        let patient = match (self.patient_id, self.patient_identifier, self.patient_identifier_system) {
+            (Some(id), _, _) => DevicePatient::Id(id),
+            (None, Some(value), Some(system)) if !value.trim().is_empty() && !system.trim().is_empty() => {
+                DevicePatient::Identifier {
+                    system: system.trim().to_string(),
+                    value: value.trim().to_string(),
+                }
+            }
+            _ => {
+                return Err(
+                    "Readings need a patient_id, or a patient_identifier and patient_identifier_system".to_string(),
+                )
+            }
+        };
+        let device = &self.device;
+        if device.reference.is_none() && device.serial_number.is_none() && device.udi.is_none() {
+            return Err("The device needs a reference, serial_number or udi".to_string());
+        }
+
+        let mut readings = Vec::new();
+        let mut rejected = Vec::new();
+        let mut ignored = 0;
+        for (index, observation) in self.readings.into_iter().enumerate() {
+            let Some(kind) = mdc_kind(&observation.code) else {
+                ignored += 1;
+                continue;
+            };
+            let unit = mdc_unit(&observation.unit).unwrap_or(observation.unit.trim());
+            if unit.is_empty() {
+                rejected.push(format!("reading {} ({}): no unit", index + 1, kind.display()));
+                continue;
+            }
+            readings.push(DeviceReading {
+                kind,
+                value: ucum(observation.value, unit),
+                effective: observation.time,
+                status: observation.status,
+            });
+        }
+
+        Ok(DeviceBatch {
+            patient,
+            encounter_id: self.encounter_id,
+            device: self.device,
+            readings,
+            rejected,
+            ignored,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_oru() {
+        let message = "MSH|^~\\&|MONITOR-7^0012345678ABCDEF^EUI-64|ICU||HIMS|\
+            20240101120000+0100||ORU^R01^ORU_R01|MSG1|P|2.6\r\
+            PID|||MRN1^^^MAIN^MR~99||DOE^JANE\r\
+            OBR|1|||182777000^monitoring of patient^SCT|||20240101120000+0100\r\
+            OBX|1||69965^MDC_DEV_MON_PHYSIO_MULTI_PARAM_MDS^MDC|1.0.0.0||||||R\r\
+            OBX|2|NM|147842^MDC_ECG_HEART_RATE^MDC|1.1.1.1|72|264864^MDC_DIM_BEAT_PER_MIN^MDC|||||R|||\
+            20240101115930+0100||||0012345678ABCDEF^^0012345678ABCDEF^EUI-64\r\
+            OBX|3|NM|150456^MDC_PULS_OXIM_SAT_O2^MDC|1.2.1.1|97|262688^MDC_DIM_PERCENT^MDC|||||F\r\
+            OBX|4|NM|150021^MDC_PRESS_BLD_NONINV_SYS^MDC|1.3.1.1|120|266016^MDC_DIM_MMHG^MDC|||||F\r\
+            OBX|5|NM|150022^MDC_PRESS_BLD_NONINV_DIA^MDC|1.3.1.2|80|266016^MDC_DIM_MMHG^MDC|||||F\r\
+            OBX|6|NM|150023^MDC_PRESS_BLD_NONINV_MEAN^MDC|1.3.1.3|93|266016^MDC_DIM_MMHG^MDC|||||F\r\
+            OBX|7|NM|8310-5^Body temperature^LN|1|37.1|Cel^degC^UCUM|||||X\r\
+            OBX|8|NM|151562^MDC_RESP_RATE^MDC|1.4.1.1|fast|264928^MDC_DIM_RESP_PER_MIN^MDC|||||R\r";
+        let (control_id, batch) = parse_oru(message).unwrap();
+        assert_eq!(control_id, "MSG1");
+        assert_eq!(
+            batch.patient,
+            DevicePatient::Identifier {
+                system: "MAIN".to_string(),
+                value: "MRN1".to_string(),
+            }
+        );
+        assert_eq!(batch.device.serial_number.as_deref(), Some("0012345678ABCDEF"));
+        assert_eq!(batch.readings.len(), 4);
+        // Mean pressure is not a vital sign, and the temperature was not obtained
+        assert_eq!(batch.ignored, 2);
+        assert_eq!(batch.rejected.len(), 1, "{:?}", batch.rejected);
+
+        let heart_rate = &batch.readings[0];
+        assert_eq!(heart_rate.value.code.as_deref(), Some("/min"));
+        assert_eq!(heart_rate.status, ObservationStatus::Preliminary);
+        assert_eq!(heart_rate.effective, Utc.with_ymd_and_hms(2024, 1, 1, 10, 59, 30).unwrap());
+        assert_eq!(batch.readings[1].effective, Utc.with_ymd_and_hms(2024, 1, 1, 11, 0, 0).unwrap());
+
+        let requests = batch.requests(Uuid::new_v4());
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].kind, VitalSignKind::HeartRate);
+        let pressure = requests.iter().find(|request| request.kind == VitalSignKind::BloodPressure).unwrap();
+        assert_eq!(pressure.component.len(), 2);
+        assert!(pressure.device.as_ref().is_some_and(|device| device.serial_number.is_some()));
+
+        assert!(parse_oru("MSH|^~\\&|MONITOR|ICU||HIMS|20240101||ADT^A01|1|P|2.5\rPID|||MRN1").is_err());
+        assert!(parse_oru("MSH|^~\\&|MONITOR|ICU||HIMS|20240101||ORU^R01|1|P|2.5\rPID|||").is_err());
+        // An identifier value alone could belong to any issuer's patient
+        assert!(parse_oru("MSH|^~\\&|MONITOR|ICU||HIMS|20240101||ORU^R01|1|P|2.5\rPID|||MRN1\rOBX|1|NM|x|1|1|x|||||F|||||SN").is_err());
+    }
+
+    #[test]
+    fn test_json_readings() {
+        let request: DeviceReadingsRequest = serde_json::from_value(serde_json::json!({
+            "patient_identifier": "MRN1",
+            "patient_identifier_system": "MAIN",
+            "device": { "serial_number": "SN-1", "manufacturer": "Acme", "model": "Vitals 300" },
+            "readings": [
+                { "code": "MDC_TEMP_BODY", "value": 98.6, "unit": "MDC_DIM_FAHR", "time": "2024-01-01T11:00:00Z" },
+                { "code": "150456", "value": 96, "unit": "%", "time": "2024-01-01T11:00:00Z" },
+                { "code": "MDC_PRESS_BLD_NONINV_SYS", "value": 120, "unit": "", "time": "2024-01-01T11:00:00Z" },
+                { "code": "MDC_CONC_GLU_CAPILLARY_WHOLEBLOOD", "value": 5.4, "unit": "mmol/L",
+                  "time": "2024-01-01T11:00:00Z" }
+            ]
+        }))
+        .unwrap();
+        let batch = request.into_batch().unwrap();
+        assert_eq!(batch.readings.len(), 2);
+        assert_eq!(batch.readings[0].value.code.as_deref(), Some("[degF]"));
+        assert_eq!(batch.rejected.len(), 1);
+        assert_eq!(batch.ignored, 1);
+        assert_eq!(batch.device.model.as_deref(), Some("Vitals 300"));
+
+        let anonymous: DeviceReadingsRequest = serde_json::from_value(serde_json::json!({
+            "patient_identifier": "MRN1",
+            "patient_identifier_system": "MAIN",
+            "device": { "manufacturer": "Acme" },
+            "readings": []
+        }))
+        .unwrap();
+        assert!(anonymous.into_batch().is_err());
+
+        let unqualified: DeviceReadingsRequest = serde_json::from_value(serde_json::json!({
+            "patient_identifier": "MRN1",
+            "device": { "serial_number": "SN-1" },
+            "readings": []
+        }))
+        .unwrap();
+        assert!(unqualified.into_batch().is_err());
+    }
+}
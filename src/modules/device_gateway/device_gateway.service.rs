@@ -0,0 +1,179 @@
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::provenance::{current_change_context, with_change_context};
+use crate::modules::device_gateway::device_gateway_mapping::{DeviceBatch, DevicePatient};
+use crate::modules::vital_sign::vital_sign_service::check_vital_sign;
+use crate::modules::vital_sign::{VitalSignRequest, VitalSignService};
+
+// Import SQL queries from separate file
+use crate::modules::device_gateway::device_gateway_sql::*;
+
+/// Device gateway settings
+#[derive(Debug, Clone)]
+pub struct DeviceGatewayConfig {
+    /// Readings of a sign within this long of one already charted from the
+    /// same device are dropped, so monitors reporting every few seconds
+    /// chart at a readable rate; repeats of the same reading are always
+    /// dropped
+    pub debounce: Duration,
+}
+
+impl Default for DeviceGatewayConfig {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(60),
+        }
+    }
+}
+
+impl DeviceGatewayConfig {
+    /// Settings from `DEVICE_DEBOUNCE_SECONDS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            debounce: std::env::var("DEVICE_DEBOUNCE_SECONDS")
+                .ok()
+                .and_then(|seconds| seconds.trim().parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.debounce),
+        }
+    }
+}
+
+/// What became of a batch of readings
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestReport {
+    pub patient_id: Option<Uuid>,
+    /// Observations recorded
+    pub recorded: Vec<Uuid>,
+    /// Readings dropped as repeats within the debounce interval
+    pub debounced: usize,
+    /// Observations that are not vital signs, or that the device could
+    /// not obtain
+    pub ignored: usize,
+    /// Readings not recorded, and why
+    pub rejected: Vec<String>,
+}
+
+/// Device gateway service filing bedside device readings as vital signs
+#[derive(Debug, Clone)]
+pub struct DeviceGatewayService {
+    pool: PgPool,
+    vital_signs: Arc<VitalSignService>,
+    config: DeviceGatewayConfig,
+}
+
+impl DeviceGatewayService {
+    /// Create new device gateway service
+    pub fn new(pool: PgPool, vital_signs: Arc<VitalSignService>, config: DeviceGatewayConfig) -> Self {
+        Self {
+            pool,
+            vital_signs,
+            config,
+        }
+    }
+
+    /// Record a device's readings as vital signs of the patient they name,
+    /// oldest first. Readings that fail validation, such as implausible
+    /// values, are reported and the rest still recorded; only a patient
+    /// that cannot be found fails the whole batch.
+    pub async fn ingest(&self, batch: DeviceBatch) -> Result<IngestReport, HimsError> {
+        let patient_id = self.resolve_patient(&batch.patient).await?;
+        let mut report = IngestReport {
+            patient_id: Some(patient_id),
+            ignored: batch.ignored,
+            rejected: batch.rejected.clone(),
+            ..IngestReport::default()
+        };
+        let device = batch.device.serial_number.as_deref().or(batch.device.reference.as_deref()).unwrap_or("-");
+        // Versions record the device as the system the readings came from
+        let context = current_change_context().unwrap_or_default().with_source(format!("device:{}", device));
+
+        for request in batch.requests(patient_id) {
+            let name = format!(
+                "{} at {}",
+                request.kind.display(),
+                request.effective.map(|at| at.to_rfc3339()).unwrap_or_default()
+            );
+            if let Err(e) = check_vital_sign(&request.clone().into()) {
+                report.rejected.push(format!("{}: {}", name, Self::reason(e)));
+                continue;
+            }
+            if self.is_repeat(&request).await? {
+                report.debounced += 1;
+                continue;
+            }
+            match with_change_context(context.clone(), self.vital_signs.create(request)).await {
+                Ok(sign) => report.recorded.push(sign.id),
+                Err(e @ HimsError::ValidationError { .. }) => {
+                    report.rejected.push(format!("{}: {}", name, Self::reason(e)))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        tracing::info!(
+            "Device {} readings for patient {}: {} recorded, {} debounced, {} rejected",
+            device,
+            patient_id,
+            report.recorded.len(),
+            report.debounced,
+            report.rejected.len()
+        );
+        Ok(report)
+    }
+
+    /// The patient the readings are of. Whether a patient was not found or
+    /// several were, the device is told only that the patient could not be
+    /// identified, so that it cannot probe for identifiers in use.
+    async fn resolve_patient(&self, patient: &DevicePatient) -> Result<Uuid, HimsError> {
+        let query = match patient {
+            DevicePatient::Id(id) => sqlx::query_scalar(FIND_ACTIVE_PATIENT).bind(*id),
+            DevicePatient::Identifier { system, value } => {
+                sqlx::query_scalar(FIND_PATIENTS_BY_IDENTIFIER).bind(system).bind(value)
+            }
+        };
+        let patients: Vec<Uuid> = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        match patients.as_slice() {
+            [patient_id] => Ok(*patient_id),
+            found => {
+                tracing::warn!("Device readings matched {} patients", found.len());
+                Err(HimsError::ValidationError {
+                    message: "Patient could not be identified".to_string(),
+                })
+            }
+        }
+    }
+
+    /// Whether the device already has a reading of the sign charted within
+    /// the debounce interval
+    async fn is_repeat(&self, request: &VitalSignRequest) -> Result<bool, HimsError> {
+        let device = request.device.clone().unwrap_or_default();
+        sqlx::query_scalar(RECENT_DEVICE_READING)
+            .bind(request.patient_id)
+            .bind(request.kind.as_str())
+            .bind(request.effective)
+            .bind(self.config.debounce.as_secs_f64())
+            .bind(device.serial_number)
+            .bind(device.reference)
+            .bind(device.udi)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))
+    }
+
+    fn reason(e: HimsError) -> String {
+        match e {
+            HimsError::ValidationError { message } => message,
+            e => e.to_string(),
+        }
+    }
+}
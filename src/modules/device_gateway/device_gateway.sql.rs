@@ -0,0 +1,36 @@
+//! Device Gateway SQL Queries
+//!
+//! This file contains all SQL queries used by the device gateway service.
+
+/// Active patients carrying the identifier of system $1 and value $2, at
+/// most two so that an ambiguous identifier can be told apart from a unique
+/// one
+pub const FIND_PATIENTS_BY_IDENTIFIER: &str = r#"
+    SELECT id
+    FROM patients
+    WHERE active = true
+      AND identifier @> jsonb_build_array(jsonb_build_object('system', $1::text, 'value', $2::text))
+    ORDER BY id
+    LIMIT 2
+"#;
+
+/// Whether the device identified by serial number $5, reference $6 and UDI
+/// $7 already has a charted reading of kind $2 for patient $1 taken within
+/// $4 seconds of $3
+pub const RECENT_DEVICE_READING: &str = r#"
+    SELECT EXISTS (
+        SELECT 1
+        FROM vital_signs
+        WHERE patient_id = $1 AND kind = $2 AND deleted_at IS NULL
+          AND effective_date_time BETWEEN $3 - make_interval(secs => $4) AND $3 + make_interval(secs => $4)
+          AND status NOT IN ('cancelled', 'entered-in-error')
+          AND device->>'serial_number' IS NOT DISTINCT FROM $5
+          AND device->>'reference' IS NOT DISTINCT FROM $6
+          AND device->>'udi' IS NOT DISTINCT FROM $7
+    )
+"#;
+
+/// Patient $1, if active
+pub const FIND_ACTIVE_PATIENT: &str = r#"
+    SELECT id FROM patients WHERE id = $1 AND active = true
+"#;
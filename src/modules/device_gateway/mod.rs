@@ -0,0 +1,54 @@
+//! Device Gateway Module
+//!
+//! This module provides ingestion of bedside device readings including:
+//! - HL7 v2 ORU^R01 unsolicited results, acknowledged as IHE PCD-01 expects
+//! - A simplified JSON mapping of IEEE 11073 numeric observations
+//! - MDC terms and units mapped to vital signs in UCUM
+//! - Validation of each reading as a vital sign, and debouncing of repeats
+//! - Observations recorded with the device they came from
+//! - Readings taken only from API keys scoped to `record_device_data`
+
+#[path = "device_gateway.controller.rs"]
+pub mod device_gateway_controller;
+#[path = "device_gateway.mapping.rs"]
+pub mod device_gateway_mapping;
+#[path = "device_gateway.service.rs"]
+pub mod device_gateway_service;
+#[path = "device_gateway.sql.rs"]
+pub mod device_gateway_sql;
+
+pub use device_gateway_controller::DeviceGatewayController;
+pub use device_gateway_mapping::{DeviceBatch, DevicePatient, DeviceReading, DeviceReadingsRequest};
+pub use device_gateway_service::{DeviceGatewayConfig, DeviceGatewayService, IngestReport};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::vital_sign::VitalSignService;
+use crate::utils::api_router::ApiRouter;
+
+/// Device Gateway Module Configuration
+pub struct DeviceGatewayModule {
+    pub service: Arc<DeviceGatewayService>,
+    pub controller: Arc<DeviceGatewayController>,
+}
+
+impl DeviceGatewayModule {
+    /// Create a new Device Gateway Module with dependency injection
+    pub fn new(db_pool: PgPool, vital_signs: Arc<VitalSignService>, config: DeviceGatewayConfig) -> Self {
+        let service = Arc::new(DeviceGatewayService::new(db_pool, vital_signs, config));
+        let controller = Arc::new(DeviceGatewayController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<DeviceGatewayService> {
+        self.service.clone()
+    }
+}
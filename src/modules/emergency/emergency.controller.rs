@@ -0,0 +1,200 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::emergency::emergency_metrics::DoorToDoctorMetrics;
+use crate::modules::emergency::emergency_service::{ArrivalRequest, BoardStatus, Disposition, EdVisit, TrackingBoard};
+use crate::modules::emergency::emergency_triage::TriageAssessment;
+use crate::modules::emergency::EmergencyService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Where to move a patient on the board, and optionally to which location
+#[derive(Debug, Deserialize)]
+pub struct MoveRequest {
+    pub status: BoardStatus,
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DepartRequest {
+    pub disposition: Disposition,
+}
+
+/// Period of arrivals to report on: the day before `to`, or before now,
+/// unless `from` is given
+#[derive(Debug, Default, Deserialize)]
+pub struct PeriodQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Emergency department controller for arrivals, triage, the tracking board
+/// and door-to-doctor times
+pub struct EmergencyController {
+    emergency_service: Arc<EmergencyService>,
+}
+
+impl EmergencyController {
+    /// Create new controller with injected service
+    pub fn new(emergency_service: Arc<EmergencyService>) -> Self {
+        Self { emergency_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/visits", Self::register_arrival, "Register a patient's arrival in the emergency department")
+            .get("/visits/:id", Self::get_visit, "Get emergency department visit by ID")
+            .post("/visits/:id/triage", Self::triage, "Triage an emergency department patient")
+            .post("/visits/:id/status", Self::move_visit, "Move a patient on the tracking board")
+            .post("/visits/:id/depart", Self::depart, "Record a patient leaving the emergency department")
+            .get("/board", Self::board, "Get the emergency department tracking board")
+            .get("/metrics/door-to-doctor", Self::door_to_doctor, "Get door-to-doctor times by triage level")
+            .with_state(self.emergency_service.clone())
+    }
+
+    /// Register an arrival; answers 409 when the patient is already in the
+    /// department
+    pub async fn register_arrival(
+        State(service): State<Arc<EmergencyService>>,
+        headers: HeaderMap,
+        Json(request): Json<ArrivalRequest>,
+    ) -> Result<(StatusCode, Json<EdVisit>), ErrorReply> {
+        tracing::info!("Registering arrival of patient {}", request.patient_id);
+
+        match service.register_arrival(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(visit) => Ok((StatusCode::CREATED, Json(visit))),
+            Err(e) => Err(Self::error_response("Failed to register arrival", e)),
+        }
+    }
+
+    /// Get emergency department visit by ID
+    pub async fn get_visit(
+        State(service): State<Arc<EmergencyService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<EdVisit>, ErrorReply> {
+        match service.get_visit(id).await {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Visit", id)),
+            Err(e) => Err(Self::error_response("Failed to get visit", e)),
+        }
+    }
+
+    /// Triage a patient on the configured scale
+    pub async fn triage(
+        State(service): State<Arc<EmergencyService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(assessment): Json<TriageAssessment>,
+    ) -> Result<Json<EdVisit>, ErrorReply> {
+        tracing::info!("Triaging visit {}", id);
+
+        match service.triage(id, assessment, extract_user_from_headers(&headers).ok()).await {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Visit", id)),
+            Err(e) => Err(Self::error_response("Failed to triage visit", e)),
+        }
+    }
+
+    /// Move a patient on the board; the caller is recorded as the doctor
+    /// who first saw the patient when treatment starts
+    pub async fn move_visit(
+        State(service): State<Arc<EmergencyService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<MoveRequest>,
+    ) -> Result<Json<EdVisit>, ErrorReply> {
+        tracing::info!("Moving visit {} to {}", id, request.status.as_str());
+
+        match service
+            .move_visit(id, request.status, request.location, extract_user_from_headers(&headers).ok())
+            .await
+        {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Visit", id)),
+            Err(e) => Err(Self::error_response("Failed to move visit", e)),
+        }
+    }
+
+    /// Record a patient leaving the department
+    pub async fn depart(
+        State(service): State<Arc<EmergencyService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<DepartRequest>,
+    ) -> Result<Json<EdVisit>, ErrorReply> {
+        tracing::info!("Visit {} departing: {}", id, request.disposition.as_str());
+
+        match service.depart(id, request.disposition).await {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Visit", id)),
+            Err(e) => Err(Self::error_response("Failed to record departure", e)),
+        }
+    }
+
+    /// Patients in the department by where they are on the board
+    pub async fn board(State(service): State<Arc<EmergencyService>>) -> Result<Json<TrackingBoard>, ErrorReply> {
+        service
+            .board()
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to get tracking board", e))
+    }
+
+    /// Door-to-doctor times of the patients who arrived in a period
+    pub async fn door_to_doctor(
+        State(service): State<Arc<EmergencyService>>,
+        Query(query): Query<PeriodQuery>,
+    ) -> Result<Json<Vec<DoorToDoctorMetrics>>, ErrorReply> {
+        let to = query.to.unwrap_or_else(Utc::now);
+        let from = query.from.unwrap_or(to - Duration::days(1));
+        service
+            .door_to_doctor(from, to)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to get door-to-doctor times", e))
+    }
+
+    fn not_found(kind: &str, id: Uuid) -> ErrorReply {
+        tracing::warn!("{} not found: {}", kind, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", kind),
+                message: format!("{} with id {} not found", kind, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
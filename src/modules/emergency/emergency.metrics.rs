@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::modules::emergency::emergency_triage::level_name;
+
+/// When a patient arrived, at what triage level, and when a doctor first
+/// saw them
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisitTiming {
+    pub level: Option<u8>,
+    pub arrived_at: DateTime<Utc>,
+    pub first_seen_at: Option<DateTime<Utc>>,
+}
+
+impl VisitTiming {
+    /// Minutes from arrival to being seen by a doctor
+    pub fn door_to_doctor_minutes(&self) -> Option<f64> {
+        self.first_seen_at
+            .map(|seen| (seen - self.arrived_at).num_seconds().max(0) as f64 / 60.0)
+    }
+}
+
+/// Door-to-doctor times of the patients of one triage level, or of all
+/// patients when `level` is `None`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DoorToDoctorMetrics {
+    pub level: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level_name: Option<&'static str>,
+    pub patients: usize,
+    /// Patients seen by a doctor; the times are theirs
+    pub seen: usize,
+    pub median_minutes: Option<f64>,
+    pub p90_minutes: Option<f64>,
+    /// Minutes within which the level should be seen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_minutes: Option<i64>,
+    /// Patients seen within the target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub within_target: Option<usize>,
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], percent: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((percent / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn metrics(level: Option<u8>, visits: &[&VisitTiming], targets: &[i64; 5]) -> DoorToDoctorMetrics {
+    let mut minutes: Vec<f64> = visits.iter().filter_map(|visit| visit.door_to_doctor_minutes()).collect();
+    minutes.sort_by(|a, b| a.total_cmp(b));
+    let target_minutes = level.map(|level| targets[usize::from(level.clamp(1, 5)) - 1]);
+    DoorToDoctorMetrics {
+        level,
+        level_name: level.map(level_name),
+        patients: visits.len(),
+        seen: minutes.len(),
+        median_minutes: percentile(&minutes, 50.0),
+        p90_minutes: percentile(&minutes, 90.0),
+        target_minutes,
+        within_target: target_minutes.map(|target| minutes.iter().filter(|m| **m <= target as f64).count()),
+    }
+}
+
+/// Door-to-doctor times of all patients, then of each triage level that
+/// had patients. `targets` gives the target minutes of levels 1 to 5.
+pub fn door_to_doctor(visits: &[VisitTiming], targets: &[i64; 5]) -> Vec<DoorToDoctorMetrics> {
+    let all: Vec<&VisitTiming> = visits.iter().collect();
+    let mut report = vec![metrics(None, &all, targets)];
+    for level in 1..=5 {
+        let of_level: Vec<&VisitTiming> = visits.iter().filter(|visit| visit.level == Some(level)).collect();
+        if !of_level.is_empty() {
+            report.push(metrics(Some(level), &of_level, targets));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn visit(level: u8, waited: Option<i64>) -> VisitTiming {
+        let arrived_at = Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap();
+        VisitTiming {
+            level: Some(level),
+            arrived_at,
+            first_seen_at: waited.map(|minutes| arrived_at + Duration::minutes(minutes)),
+        }
+    }
+
+    #[test]
+    fn test_door_to_doctor() {
+        let targets = [0, 15, 30, 60, 120];
+        let visits = vec![
+            visit(2, Some(10)),
+            visit(2, Some(20)),
+            visit(2, Some(12)),
+            visit(3, Some(45)),
+            visit(3, None),
+        ];
+        let report = door_to_doctor(&visits, &targets);
+        assert_eq!(report.len(), 3, "all patients, level 2 and level 3");
+
+        let overall = &report[0];
+        assert_eq!((overall.patients, overall.seen), (5, 4));
+        assert_eq!(overall.median_minutes, Some(12.0));
+        assert_eq!(overall.within_target, None);
+
+        let level_2 = &report[1];
+        assert_eq!(level_2.level_name, Some("emergent"));
+        assert_eq!(level_2.median_minutes, Some(12.0));
+        assert_eq!(level_2.p90_minutes, Some(20.0));
+        assert_eq!((level_2.target_minutes, level_2.within_target), (Some(15), Some(2)));
+
+        let level_3 = &report[2];
+        assert_eq!((level_3.patients, level_3.seen, level_3.within_target), (2, 1, Some(0)));
+        assert!(door_to_doctor(&[], &targets)[0].median_minutes.is_none());
+    }
+}
@@ -0,0 +1,648 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::{AuthResult, UrgencyLevel, UrgencySource};
+use crate::modules::emergency::emergency_metrics::{door_to_doctor, DoorToDoctorMetrics, VisitTiming};
+use crate::modules::emergency::emergency_triage::{check_assessment, triage, TriageAssessment, TriageResult, TriageScale};
+
+// Import SQL queries from separate file
+use crate::modules::emergency::emergency_sql::*;
+
+/// Emergency department settings
+#[derive(Debug, Clone)]
+pub struct EmergencyConfig {
+    /// Scale patients are triaged on
+    pub triage_scale: TriageScale,
+    /// Minutes within which a patient of triage levels 1 to 5 should be
+    /// seen by a doctor
+    pub door_to_doctor_targets: [i64; 5],
+}
+
+impl Default for EmergencyConfig {
+    fn default() -> Self {
+        Self {
+            triage_scale: TriageScale::Esi,
+            door_to_doctor_targets: [0, 15, 30, 60, 120],
+        }
+    }
+}
+
+impl EmergencyConfig {
+    /// Settings from `ED_TRIAGE_SCALE` (`esi` or `ctas`) and
+    /// `ED_DOOR_TO_DOCTOR_TARGETS`, five comma-separated minutes
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        let targets = var("ED_DOOR_TO_DOCTOR_TARGETS").and_then(|targets| {
+            let minutes: Vec<i64> = targets
+                .split(',')
+                .map(|minutes| minutes.trim().parse())
+                .collect::<Result<_, _>>()
+                .ok()?;
+            minutes.try_into().ok()
+        });
+
+        Self {
+            triage_scale: var("ED_TRIAGE_SCALE")
+                .and_then(|scale| TriageScale::from_string(&scale.to_lowercase()))
+                .unwrap_or(defaults.triage_scale),
+            door_to_doctor_targets: targets.unwrap_or(defaults.door_to_doctor_targets),
+        }
+    }
+}
+
+/// Where a patient is on the tracking board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BoardStatus {
+    Waiting,
+    InTreatment,
+    AwaitingResults,
+    Departed,
+}
+
+impl BoardStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BoardStatus::Waiting => "waiting",
+            BoardStatus::InTreatment => "in-treatment",
+            BoardStatus::AwaitingResults => "awaiting-results",
+            BoardStatus::Departed => "departed",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "waiting" => Some(BoardStatus::Waiting),
+            "in-treatment" => Some(BoardStatus::InTreatment),
+            "awaiting-results" => Some(BoardStatus::AwaitingResults),
+            "departed" => Some(BoardStatus::Departed),
+            _ => None,
+        }
+    }
+}
+
+/// Where a patient went on leaving the department
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Disposition {
+    Admitted,
+    Discharged,
+    Transferred,
+    LeftWithoutBeingSeen,
+    Deceased,
+}
+
+impl Disposition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Disposition::Admitted => "admitted",
+            Disposition::Discharged => "discharged",
+            Disposition::Transferred => "transferred",
+            Disposition::LeftWithoutBeingSeen => "left-without-being-seen",
+            Disposition::Deceased => "deceased",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "admitted" => Some(Disposition::Admitted),
+            "discharged" => Some(Disposition::Discharged),
+            "transferred" => Some(Disposition::Transferred),
+            "left-without-being-seen" => Some(Disposition::LeftWithoutBeingSeen),
+            "deceased" => Some(Disposition::Deceased),
+            _ => None,
+        }
+    }
+}
+
+/// A triage of a visit, and the assessment it was made from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageRecord {
+    #[serde(flatten)]
+    pub result: TriageResult,
+    pub assessment: TriageAssessment,
+    pub triaged_at: DateTime<Utc>,
+    pub triaged_by: Option<Uuid>,
+}
+
+/// A patient's stay in the emergency department
+#[derive(Debug, Clone, Serialize)]
+pub struct EdVisit {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub chief_complaint: String,
+    pub arrival_mode: Option<String>,
+    pub arrived_at: DateTime<Utc>,
+    pub status: BoardStatus,
+    /// The latest triage; `None` until the patient is triaged
+    pub triage: Option<TriageRecord>,
+    /// Bed, bay or area
+    pub location: Option<String>,
+    /// When and by whom the patient was first seen by a doctor
+    pub first_seen_at: Option<DateTime<Utc>>,
+    pub seen_by: Option<Uuid>,
+    pub departed_at: Option<DateTime<Utc>>,
+    pub disposition: Option<Disposition>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn visit_from_row(row: &PgRow) -> EdVisit {
+    EdVisit {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        chief_complaint: row.get("chief_complaint"),
+        arrival_mode: row.get("arrival_mode"),
+        arrived_at: row.get("arrived_at"),
+        status: BoardStatus::from_string(row.get("status")).unwrap_or(BoardStatus::Waiting),
+        triage: row
+            .get::<Option<serde_json::Value>, _>("triage")
+            .and_then(|triage| serde_json::from_value(triage).ok()),
+        location: row.get("location"),
+        first_seen_at: row.get("first_seen_at"),
+        seen_by: row.get("seen_by"),
+        departed_at: row.get("departed_at"),
+        disposition: row
+            .get::<Option<String>, _>("disposition")
+            .and_then(|disposition| Disposition::from_string(&disposition)),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+impl EdVisit {
+    /// Move the patient on the board. Entering treatment the first time
+    /// records when and by whom the patient was first seen; a patient
+    /// awaits results only once seen.
+    pub fn move_to(
+        &mut self,
+        status: BoardStatus,
+        location: Option<String>,
+        now: DateTime<Utc>,
+        clinician: Option<Uuid>,
+    ) -> Result<(), String> {
+        if self.status == BoardStatus::Departed {
+            return Err(format!("Visit {} has departed", self.id));
+        }
+        match status {
+            BoardStatus::Departed => return Err("A patient departs with a disposition".to_string()),
+            BoardStatus::InTreatment if self.first_seen_at.is_none() => {
+                self.first_seen_at = Some(now);
+                self.seen_by = clinician;
+            }
+            BoardStatus::AwaitingResults if self.first_seen_at.is_none() => {
+                return Err(format!("The patient of visit {} has not been seen by a doctor", self.id));
+            }
+            _ => {}
+        }
+        self.status = status;
+        if location.is_some() {
+            self.location = location;
+        }
+        Ok(())
+    }
+
+    /// Record the patient leaving the department
+    pub fn depart(&mut self, disposition: Disposition, now: DateTime<Utc>) -> Result<(), String> {
+        if self.status == BoardStatus::Departed {
+            return Err(format!("Visit {} has departed", self.id));
+        }
+        if disposition == Disposition::LeftWithoutBeingSeen && self.first_seen_at.is_some() {
+            return Err(format!("The patient of visit {} has been seen by a doctor", self.id));
+        }
+        self.status = BoardStatus::Departed;
+        self.departed_at = Some(now);
+        self.disposition = Some(disposition);
+        Ok(())
+    }
+}
+
+/// A patient's arrival as clients send it
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArrivalRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub chief_complaint: String,
+    /// E.g. ambulance, walk-in, police
+    pub arrival_mode: Option<String>,
+    /// Now when not given
+    pub arrived_at: Option<DateTime<Utc>>,
+}
+
+/// A patient on the tracking board
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardEntry {
+    #[serde(flatten)]
+    pub visit: EdVisit,
+    pub minutes_since_arrival: i64,
+    /// Minutes within which the patient's triage level should be seen by a
+    /// doctor, while the patient has not been
+    pub target_minutes: Option<i64>,
+    pub target_breached: bool,
+}
+
+/// Patients in the department by where they are on the board; each list
+/// has untriaged patients first, then the most acute, then the longest
+/// waiting
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrackingBoard {
+    pub waiting: Vec<BoardEntry>,
+    pub in_treatment: Vec<BoardEntry>,
+    pub awaiting_results: Vec<BoardEntry>,
+}
+
+/// Lay open visits out on the board as of `now`
+pub fn tracking_board(visits: Vec<EdVisit>, now: DateTime<Utc>, targets: &[i64; 5]) -> TrackingBoard {
+    let mut board = TrackingBoard::default();
+    for visit in visits {
+        let minutes_since_arrival = (now - visit.arrived_at).num_minutes().max(0);
+        let target_minutes = match (&visit.triage, visit.first_seen_at) {
+            (Some(triage), None) => Some(targets[usize::from(triage.result.level.clamp(1, 5)) - 1]),
+            _ => None,
+        };
+        let entry = BoardEntry {
+            target_breached: target_minutes.is_some_and(|target| minutes_since_arrival > target),
+            minutes_since_arrival,
+            target_minutes,
+            visit,
+        };
+        match entry.visit.status {
+            BoardStatus::Waiting => board.waiting.push(entry),
+            BoardStatus::InTreatment => board.in_treatment.push(entry),
+            BoardStatus::AwaitingResults => board.awaiting_results.push(entry),
+            BoardStatus::Departed => {}
+        }
+    }
+    board
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Database error, or a conflict when a unique constraint was violated
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+/// Emergency department service for arrivals, triage, the tracking board
+/// and door-to-doctor times
+pub struct EmergencyService {
+    pool: PgPool,
+    config: EmergencyConfig,
+}
+
+impl EmergencyService {
+    /// Create new emergency department service
+    pub fn new(pool: PgPool, config: EmergencyConfig) -> Self {
+        Self { pool, config }
+    }
+
+    /// Check an encounter a visit is linked to is of the visit's patient
+    async fn check_encounter(conn: &mut PgConnection, encounter_id: Uuid, patient_id: Uuid) -> Result<(), HimsError> {
+        let subject: Option<Uuid> = sqlx::query(GET_ENCOUNTER_SUBJECT)
+            .bind(encounter_id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?
+            .map(|row| row.get("subject"));
+        match subject {
+            Some(subject) if subject == patient_id => Ok(()),
+            Some(_) => Err(HimsError::ValidationError {
+                message: format!("Encounter {} is not of patient {}", encounter_id, patient_id),
+            }),
+            None => Err(HimsError::ValidationError {
+                message: format!("Encounter {} does not exist", encounter_id),
+            }),
+        }
+    }
+
+    /// Register a patient's arrival, putting them on the board as waiting
+    pub async fn register_arrival(&self, request: ArrivalRequest, created_by: Option<Uuid>) -> Result<EdVisit, HimsError> {
+        if request.chief_complaint.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "An arrival needs a chief complaint".to_string(),
+            });
+        }
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        if let Some(encounter_id) = request.encounter_id {
+            Self::check_encounter(&mut conn, encounter_id, request.patient_id).await?;
+        }
+        let row = sqlx::query(INSERT_VISIT)
+            .bind(Uuid::new_v4())
+            .bind(request.patient_id)
+            .bind(request.encounter_id)
+            .bind(request.chief_complaint.trim())
+            .bind(&request.arrival_mode)
+            .bind(request.arrived_at.unwrap_or_else(Utc::now))
+            .bind(created_by)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| write_error(e, || format!("Patient {} is already in the department", request.patient_id)))?;
+        Ok(visit_from_row(&row))
+    }
+
+    /// Get visit by ID
+    pub async fn get_visit(&self, id: Uuid) -> Result<Option<EdVisit>, HimsError> {
+        let row = sqlx::query(GET_VISIT_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(visit_from_row))
+    }
+
+    async fn lock_visit(conn: &mut PgConnection, id: Uuid) -> Result<Option<EdVisit>, HimsError> {
+        let row = sqlx::query(LOCK_VISIT)
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(visit_from_row))
+    }
+
+    /// Triage a patient on the configured scale, or re-triage them,
+    /// raising or lowering the urgency of requests about them. `None` if
+    /// there is no such visit.
+    pub async fn triage(
+        &self,
+        id: Uuid,
+        assessment: TriageAssessment,
+        triaged_by: Option<Uuid>,
+    ) -> Result<Option<EdVisit>, HimsError> {
+        check_assessment(&assessment).map_err(|message| HimsError::ValidationError { message })?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(visit) = Self::lock_visit(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        if visit.status == BoardStatus::Departed {
+            return Err(HimsError::ConflictError {
+                message: format!("Visit {} has departed", id),
+            });
+        }
+        let record = TriageRecord {
+            result: triage(self.config.triage_scale, &assessment),
+            assessment,
+            triaged_at: Utc::now(),
+            triaged_by,
+        };
+        sqlx::query(UPDATE_VISIT_TRIAGE)
+            .bind(id)
+            .bind(serde_json::to_value(&record).unwrap_or_default())
+            .bind(i16::from(record.result.level))
+            .bind(record.result.urgency().as_str())
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_visit(id).await
+    }
+
+    /// Move a visit on with `step`, or `None` if there is no such visit
+    async fn progress(
+        &self,
+        id: Uuid,
+        step: impl FnOnce(&mut EdVisit) -> Result<(), String>,
+    ) -> Result<Option<EdVisit>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut visit) = Self::lock_visit(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        step(&mut visit).map_err(|message| HimsError::ConflictError { message })?;
+        sqlx::query(UPDATE_VISIT_PROGRESS)
+            .bind(id)
+            .bind(visit.status.as_str())
+            .bind(&visit.location)
+            .bind(visit.first_seen_at)
+            .bind(visit.seen_by)
+            .bind(visit.departed_at)
+            .bind(visit.disposition.map(|disposition| disposition.as_str()))
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_visit(id).await
+    }
+
+    /// Move a patient on the board, optionally to another location
+    pub async fn move_visit(
+        &self,
+        id: Uuid,
+        status: BoardStatus,
+        location: Option<String>,
+        clinician: Option<Uuid>,
+    ) -> Result<Option<EdVisit>, HimsError> {
+        self.progress(id, |visit| visit.move_to(status, location, Utc::now(), clinician))
+            .await
+    }
+
+    /// Record a patient leaving the department, taking them off the board
+    pub async fn depart(&self, id: Uuid, disposition: Disposition) -> Result<Option<EdVisit>, HimsError> {
+        self.progress(id, |visit| visit.depart(disposition, Utc::now())).await
+    }
+
+    /// Patients in the department
+    pub async fn board(&self) -> Result<TrackingBoard, HimsError> {
+        let rows = sqlx::query(GET_OPEN_VISITS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let visits = rows.iter().map(visit_from_row).collect();
+        Ok(tracking_board(visits, Utc::now(), &self.config.door_to_doctor_targets))
+    }
+
+    /// Door-to-doctor times of patients who arrived in `[from, to)`, overall
+    /// and by triage level
+    pub async fn door_to_doctor(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DoorToDoctorMetrics>, HimsError> {
+        if to <= from {
+            return Err(HimsError::ValidationError {
+                message: "A period's end must be after its start".to_string(),
+            });
+        }
+        let rows = sqlx::query(GET_VISIT_TIMINGS)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let timings: Vec<VisitTiming> = rows
+            .iter()
+            .map(|row| VisitTiming {
+                level: row.get::<Option<i16>, _>("triage_level").and_then(|level| u8::try_from(level).ok()),
+                arrived_at: row.get("arrived_at"),
+                first_seen_at: row.get("first_seen_at"),
+            })
+            .collect();
+        Ok(door_to_doctor(&timings, &self.config.door_to_doctor_targets))
+    }
+}
+
+/// Urgency of patients in the emergency department, from their latest
+/// triage, for authorization decisions
+pub struct TriageUrgency {
+    pool: PgPool,
+}
+
+impl TriageUrgency {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UrgencySource for TriageUrgency {
+    async fn patient_urgency(&self, patient_id: Uuid) -> AuthResult<Option<UrgencyLevel>> {
+        let urgency: Option<String> = sqlx::query(GET_OPEN_VISIT_URGENCY)
+            .bind(patient_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get("urgency"));
+        Ok(urgency.and_then(|urgency| UrgencyLevel::from_string(&urgency)))
+    }
+
+    async fn patient_urgency_batch(&self, patient_ids: &[Uuid]) -> AuthResult<HashMap<Uuid, UrgencyLevel>> {
+        if patient_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let rows = sqlx::query(GET_OPEN_VISITS_URGENCY)
+            .bind(patient_ids)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let urgency: String = row.get("urgency");
+                Some((row.get("patient_id"), UrgencyLevel::from_string(&urgency)?))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn visit(arrived_minutes_ago: i64) -> EdVisit {
+        let now = Utc::now();
+        EdVisit {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            encounter_id: None,
+            chief_complaint: "Chest pain".to_string(),
+            arrival_mode: Some("ambulance".to_string()),
+            arrived_at: now - Duration::minutes(arrived_minutes_ago),
+            status: BoardStatus::Waiting,
+            triage: None,
+            location: None,
+            first_seen_at: None,
+            seen_by: None,
+            departed_at: None,
+            disposition: None,
+            created_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn triaged(mut visit: EdVisit, assessment: TriageAssessment) -> EdVisit {
+        visit.triage = Some(TriageRecord {
+            result: triage(TriageScale::Esi, &assessment),
+            assessment,
+            triaged_at: visit.arrived_at,
+            triaged_by: None,
+        });
+        visit
+    }
+
+    #[test]
+    fn test_visit_moves_on_board() {
+        let mut visit = visit(10);
+        let now = Utc::now();
+        let doctor = Uuid::new_v4();
+        assert!(visit.move_to(BoardStatus::AwaitingResults, None, now, Some(doctor)).is_err(), "not seen yet");
+
+        visit.move_to(BoardStatus::InTreatment, Some("Resus 1".to_string()), now, Some(doctor)).unwrap();
+        assert_eq!((visit.first_seen_at, visit.seen_by), (Some(now), Some(doctor)));
+        visit
+            .move_to(BoardStatus::AwaitingResults, None, now + Duration::minutes(30), None)
+            .unwrap();
+        visit
+            .move_to(BoardStatus::InTreatment, None, now + Duration::minutes(90), Some(Uuid::new_v4()))
+            .unwrap();
+        assert_eq!(visit.first_seen_at, Some(now), "first seen stays when treatment resumes");
+        assert_eq!(visit.location.as_deref(), Some("Resus 1"));
+
+        assert!(visit.move_to(BoardStatus::Departed, None, now, None).is_err());
+        assert!(visit.depart(Disposition::LeftWithoutBeingSeen, now).is_err(), "was seen");
+        visit.depart(Disposition::Admitted, now).unwrap();
+        assert_eq!(visit.status, BoardStatus::Departed);
+        assert!(visit.move_to(BoardStatus::Waiting, None, now, None).is_err());
+        assert!(visit.depart(Disposition::Discharged, now).is_err());
+    }
+
+    #[test]
+    fn test_tracking_board() {
+        let targets = EmergencyConfig::default().door_to_doctor_targets;
+        let emergent = triaged(
+            visit(20),
+            TriageAssessment {
+                high_risk: true,
+                ..Default::default()
+            },
+        );
+        let minor = triaged(visit(20), TriageAssessment::default());
+        let mut seen = triaged(visit(90), TriageAssessment::default());
+        seen.move_to(BoardStatus::InTreatment, None, Utc::now(), None).unwrap();
+        let untriaged = visit(5);
+
+        let board = tracking_board(vec![untriaged, emergent, minor, seen], Utc::now(), &targets);
+        assert_eq!(board.waiting.len(), 3);
+        assert_eq!(board.in_treatment.len(), 1);
+        assert!(board.awaiting_results.is_empty());
+
+        assert_eq!(board.waiting[0].target_minutes, None, "not triaged");
+        assert_eq!(board.waiting[1].target_minutes, Some(15));
+        assert!(board.waiting[1].target_breached, "level 2 waiting 20 minutes");
+        assert!(!board.waiting[2].target_breached, "level 5 waiting 20 minutes");
+        assert_eq!(board.in_treatment[0].target_minutes, None, "already seen");
+        assert_eq!(board.in_treatment[0].minutes_since_arrival, 90);
+    }
+
+    #[test]
+    fn test_statuses() {
+        for status in [
+            BoardStatus::Waiting,
+            BoardStatus::InTreatment,
+            BoardStatus::AwaitingResults,
+            BoardStatus::Departed,
+        ] {
+            assert_eq!(BoardStatus::from_string(status.as_str()), Some(status));
+        }
+        assert_eq!(
+            Disposition::from_string("left-without-being-seen"),
+            Some(Disposition::LeftWithoutBeingSeen)
+        );
+        assert_eq!(UrgencyLevel::from_string(UrgencyLevel::Critical.as_str()), Some(UrgencyLevel::Critical));
+    }
+}
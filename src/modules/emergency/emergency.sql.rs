@@ -0,0 +1,81 @@
+//! Emergency Department SQL Queries
+//!
+//! This file contains all SQL queries used by the emergency department service.
+
+/// Register a patient's arrival
+pub const INSERT_VISIT: &str = r#"
+    INSERT INTO ed_visits (id, patient_id, encounter_id, chief_complaint, arrival_mode, arrived_at, created_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    RETURNING id, patient_id, encounter_id, chief_complaint, arrival_mode, arrived_at, status, triage, urgency,
+              location, first_seen_at, seen_by, departed_at, disposition, created_by, created_at, updated_at
+"#;
+
+/// Get visit by ID
+pub const GET_VISIT_BY_ID: &str = r#"
+    SELECT id, patient_id, encounter_id, chief_complaint, arrival_mode, arrived_at, status, triage, urgency,
+           location, first_seen_at, seen_by, departed_at, disposition, created_by, created_at, updated_at
+    FROM ed_visits
+    WHERE id = $1
+"#;
+
+/// Lock a visit while it is triaged or moved on the board
+pub const LOCK_VISIT: &str = r#"
+    SELECT id, patient_id, encounter_id, chief_complaint, arrival_mode, arrived_at, status, triage, urgency,
+           location, first_seen_at, seen_by, departed_at, disposition, created_by, created_at, updated_at
+    FROM ed_visits
+    WHERE id = $1
+    FOR UPDATE
+"#;
+
+/// Patients in the department: untriaged first, then most acute, then
+/// longest waiting
+pub const GET_OPEN_VISITS: &str = r#"
+    SELECT id, patient_id, encounter_id, chief_complaint, arrival_mode, arrived_at, status, triage, urgency,
+           location, first_seen_at, seen_by, departed_at, disposition, created_by, created_at, updated_at
+    FROM ed_visits
+    WHERE status <> 'departed'
+    ORDER BY triage_level NULLS FIRST, arrived_at
+"#;
+
+/// Save a visit's triage
+pub const UPDATE_VISIT_TRIAGE: &str = r#"
+    UPDATE ed_visits
+    SET triage = $2, triage_level = $3, urgency = $4
+    WHERE id = $1
+"#;
+
+/// Save where a visit is on the board
+pub const UPDATE_VISIT_PROGRESS: &str = r#"
+    UPDATE ed_visits
+    SET status = $2, location = $3, first_seen_at = $4, seen_by = $5, departed_at = $6, disposition = $7
+    WHERE id = $1
+"#;
+
+/// Arrival, triage level and first doctor contact of visits that arrived
+/// in [$1, $2)
+pub const GET_VISIT_TIMINGS: &str = r#"
+    SELECT triage_level, arrived_at, first_seen_at
+    FROM ed_visits
+    WHERE arrived_at >= $1 AND arrived_at < $2
+"#;
+
+/// Urgency of a patient's open visit, if they are in the department
+pub const GET_OPEN_VISIT_URGENCY: &str = r#"
+    SELECT urgency
+    FROM ed_visits
+    WHERE patient_id = $1 AND status <> 'departed' AND urgency IS NOT NULL
+"#;
+
+/// Urgency of the open visits of patients $1, one per patient in the department
+pub const GET_OPEN_VISITS_URGENCY: &str = r#"
+    SELECT DISTINCT ON (patient_id) patient_id, urgency
+    FROM ed_visits
+    WHERE patient_id = ANY($1) AND status <> 'departed' AND urgency IS NOT NULL
+"#;
+
+/// Patient an encounter is of
+pub const GET_ENCOUNTER_SUBJECT: &str = r#"
+    SELECT subject
+    FROM encounters
+    WHERE id = $1
+"#;
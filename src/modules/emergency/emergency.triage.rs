@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+
+use crate::modules::authorization::UrgencyLevel;
+
+/// Five-level triage scale the department assigns acuity with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriageScale {
+    /// Emergency Severity Index, version 4
+    Esi,
+    /// Canadian Triage and Acuity Scale
+    Ctas,
+}
+
+impl TriageScale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriageScale::Esi => "esi",
+            TriageScale::Ctas => "ctas",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "esi" => Some(TriageScale::Esi),
+            "ctas" => Some(TriageScale::Ctas),
+            _ => None,
+        }
+    }
+}
+
+/// What the triage nurse found, as both scales use it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriageAssessment {
+    /// Needs an immediate life-saving intervention, e.g. airway, defibrillation
+    #[serde(default)]
+    pub requires_immediate_intervention: bool,
+    /// A high-risk presentation, e.g. chest pain suggestive of ACS, stroke
+    /// symptoms, suicidal ideation
+    #[serde(default)]
+    pub high_risk: bool,
+    /// New confusion, lethargy or disorientation
+    #[serde(default)]
+    pub altered_mental_status: bool,
+    /// Pain on a 0-10 scale
+    pub pain_score: Option<u8>,
+    /// Distinct resources, e.g. labs, imaging, IV fluids, specialist
+    /// consultation, expected to be needed
+    #[serde(default)]
+    pub expected_resources: u8,
+    pub heart_rate: Option<u16>,
+    pub respiratory_rate: Option<u16>,
+    pub oxygen_saturation: Option<u8>,
+    /// Glasgow Coma Scale, 3-15
+    pub gcs: Option<u8>,
+    /// Age in months, which sets the danger-zone vital signs; an adult
+    /// when not given
+    pub age_months: Option<u32>,
+}
+
+/// Acuity assigned at triage and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriageResult {
+    pub scale: TriageScale,
+    /// 1 (most acute) to 5
+    pub level: u8,
+    pub reasons: Vec<String>,
+}
+
+impl TriageResult {
+    /// Urgency the level carries into authorization decisions
+    pub fn urgency(&self) -> UrgencyLevel {
+        urgency_for_level(self.level)
+    }
+}
+
+/// Urgency of a triage level: resuscitation is critical, emergent an
+/// emergency, urgent urgent and the rest routine
+pub fn urgency_for_level(level: u8) -> UrgencyLevel {
+    match level {
+        1 => UrgencyLevel::Critical,
+        2 => UrgencyLevel::Emergency,
+        3 => UrgencyLevel::Urgent,
+        _ => UrgencyLevel::Routine,
+    }
+}
+
+/// Name of a triage level, the same on both scales
+pub fn level_name(level: u8) -> &'static str {
+    match level {
+        1 => "resuscitation",
+        2 => "emergent",
+        3 => "urgent",
+        4 => "less-urgent",
+        _ => "non-urgent",
+    }
+}
+
+/// Check an assessment's values are in range
+pub fn check_assessment(assessment: &TriageAssessment) -> Result<(), String> {
+    if assessment.pain_score.is_some_and(|pain| pain > 10) {
+        return Err("A pain score is 0 to 10".to_string());
+    }
+    if assessment.oxygen_saturation.is_some_and(|saturation| saturation > 100) {
+        return Err("Oxygen saturation is at most 100%".to_string());
+    }
+    if assessment.gcs.is_some_and(|gcs| !(3..=15).contains(&gcs)) {
+        return Err("A Glasgow Coma Scale score is 3 to 15".to_string());
+    }
+    Ok(())
+}
+
+/// Heart and respiratory rates above which an ESI level 3 patient is
+/// considered for level 2, by age
+fn danger_zone(age_months: Option<u32>) -> (u16, u16) {
+    match age_months {
+        Some(age) if age < 3 => (180, 50),
+        Some(age) if age < 36 => (160, 40),
+        Some(age) if age < 96 => (140, 30),
+        _ => (100, 20),
+    }
+}
+
+/// Assign an acuity level on `scale`
+pub fn triage(scale: TriageScale, assessment: &TriageAssessment) -> TriageResult {
+    let (level, reasons) = match scale {
+        TriageScale::Esi => esi(assessment),
+        TriageScale::Ctas => ctas(assessment),
+    };
+    TriageResult { scale, level, reasons }
+}
+
+/// The ESI v4 algorithm: life-saving intervention (1), then high risk,
+/// altered mentation or severe pain (2), then resources needed, with
+/// danger-zone vital signs moving a level 3 patient up to 2
+fn esi(assessment: &TriageAssessment) -> (u8, Vec<String>) {
+    if assessment.requires_immediate_intervention {
+        return (1, vec!["Requires immediate life-saving intervention".to_string()]);
+    }
+
+    let mut reasons = Vec::new();
+    if assessment.high_risk {
+        reasons.push("High-risk situation".to_string());
+    }
+    if assessment.altered_mental_status {
+        reasons.push("Confused, lethargic or disoriented".to_string());
+    }
+    if let Some(pain) = assessment.pain_score.filter(|pain| *pain >= 7) {
+        reasons.push(format!("Severe pain ({}/10)", pain));
+    }
+    if !reasons.is_empty() {
+        return (2, reasons);
+    }
+
+    match assessment.expected_resources {
+        0 => return (5, vec!["No resources expected".to_string()]),
+        1 => return (4, vec!["One resource expected".to_string()]),
+        resources => reasons.push(format!("{} resources expected", resources)),
+    }
+
+    let (heart_rate_limit, respiratory_rate_limit) = danger_zone(assessment.age_months);
+    if let Some(heart_rate) = assessment.heart_rate.filter(|rate| *rate > heart_rate_limit) {
+        reasons.push(format!("Danger-zone heart rate ({}/min)", heart_rate));
+    }
+    if let Some(respiratory_rate) = assessment.respiratory_rate.filter(|rate| *rate > respiratory_rate_limit) {
+        reasons.push(format!("Danger-zone respiratory rate ({}/min)", respiratory_rate));
+    }
+    if let Some(saturation) = assessment.oxygen_saturation.filter(|saturation| *saturation < 92) {
+        reasons.push(format!("Oxygen saturation {}%", saturation));
+    }
+    match reasons.len() > 1 {
+        true => (2, reasons),
+        false => (3, reasons),
+    }
+}
+
+/// CTAS by its first-order modifiers: respiratory distress by oxygen
+/// saturation, level of consciousness by GCS, pain severity, and the
+/// presenting complaint's risk and expected workup
+fn ctas(assessment: &TriageAssessment) -> (u8, Vec<String>) {
+    let mut by_level: [Vec<String>; 5] = Default::default();
+    let mut add = |level: usize, reason: String| by_level[level - 1].push(reason);
+
+    if assessment.requires_immediate_intervention {
+        add(1, "Requires resuscitation".to_string());
+    }
+    match assessment.oxygen_saturation {
+        Some(saturation) if saturation < 90 => add(1, format!("Severe respiratory distress (SpO2 {}%)", saturation)),
+        Some(saturation) if saturation < 92 => add(2, format!("Moderate respiratory distress (SpO2 {}%)", saturation)),
+        Some(saturation) if saturation < 95 => add(3, format!("Mild respiratory distress (SpO2 {}%)", saturation)),
+        _ => {}
+    }
+    match assessment.gcs {
+        Some(gcs) if gcs <= 9 => add(1, format!("Unconscious (GCS {})", gcs)),
+        Some(gcs) if gcs <= 13 => add(2, format!("Altered level of consciousness (GCS {})", gcs)),
+        _ => {}
+    }
+    if assessment.altered_mental_status {
+        add(2, "Altered level of consciousness".to_string());
+    }
+    if assessment.high_risk {
+        add(2, "High-risk presentation".to_string());
+    }
+    match assessment.pain_score {
+        Some(pain) if pain >= 8 => add(2, format!("Severe pain ({}/10)", pain)),
+        Some(pain) if pain >= 4 => add(3, format!("Moderate pain ({}/10)", pain)),
+        Some(pain) if pain >= 1 => add(4, format!("Mild pain ({}/10)", pain)),
+        _ => {}
+    }
+    match assessment.expected_resources {
+        0 => {}
+        1 => add(4, "One resource expected".to_string()),
+        resources => add(3, format!("{} resources expected", resources)),
+    }
+
+    match by_level.iter().position(|reasons| !reasons.is_empty()) {
+        Some(index) => (index as u8 + 1, std::mem::take(&mut by_level[index])),
+        None => (5, vec!["No modifiers present".to_string()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_esi() {
+        let arrest = TriageAssessment {
+            requires_immediate_intervention: true,
+            ..Default::default()
+        };
+        assert_eq!(triage(TriageScale::Esi, &arrest).level, 1);
+
+        let chest_pain = TriageAssessment {
+            high_risk: true,
+            expected_resources: 3,
+            ..Default::default()
+        };
+        assert_eq!(triage(TriageScale::Esi, &chest_pain).level, 2);
+
+        let abdominal_pain = TriageAssessment {
+            pain_score: Some(5),
+            expected_resources: 2,
+            heart_rate: Some(88),
+            respiratory_rate: Some(16),
+            oxygen_saturation: Some(98),
+            ..Default::default()
+        };
+        assert_eq!(triage(TriageScale::Esi, &abdominal_pain).level, 3);
+
+        let tachycardic = TriageAssessment {
+            heart_rate: Some(125),
+            ..abdominal_pain.clone()
+        };
+        let result = triage(TriageScale::Esi, &tachycardic);
+        assert_eq!(result.level, 2, "danger-zone vital signs: {:?}", result.reasons);
+        assert_eq!(result.urgency(), UrgencyLevel::Emergency);
+
+        let infant = TriageAssessment {
+            age_months: Some(12),
+            ..tachycardic.clone()
+        };
+        assert_eq!(triage(TriageScale::Esi, &infant).level, 3, "125/min is normal at one year");
+
+        let sprain = TriageAssessment {
+            pain_score: Some(3),
+            expected_resources: 1,
+            ..Default::default()
+        };
+        assert_eq!(triage(TriageScale::Esi, &sprain).level, 4);
+        assert_eq!(triage(TriageScale::Esi, &TriageAssessment::default()).level, 5);
+    }
+
+    #[test]
+    fn test_ctas() {
+        let hypoxic = TriageAssessment {
+            oxygen_saturation: Some(88),
+            pain_score: Some(9),
+            ..Default::default()
+        };
+        let result = triage(TriageScale::Ctas, &hypoxic);
+        assert_eq!(result.level, 1);
+        assert_eq!(result.reasons, vec!["Severe respiratory distress (SpO2 88%)".to_string()]);
+        assert_eq!(result.urgency(), UrgencyLevel::Critical);
+
+        let confused = TriageAssessment {
+            gcs: Some(12),
+            ..Default::default()
+        };
+        assert_eq!(triage(TriageScale::Ctas, &confused).level, 2);
+
+        let moderate_pain = TriageAssessment {
+            pain_score: Some(6),
+            expected_resources: 1,
+            ..Default::default()
+        };
+        assert_eq!(triage(TriageScale::Ctas, &moderate_pain).level, 3);
+
+        let mild_pain = TriageAssessment {
+            pain_score: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(triage(TriageScale::Ctas, &mild_pain).level, 4);
+        assert_eq!(triage(TriageScale::Ctas, &TriageAssessment::default()).level, 5);
+        assert_eq!(urgency_for_level(5), UrgencyLevel::Routine);
+    }
+
+    #[test]
+    fn test_check_assessment() {
+        assert!(check_assessment(&TriageAssessment::default()).is_ok());
+        let out_of_range = TriageAssessment {
+            gcs: Some(2),
+            ..Default::default()
+        };
+        assert!(check_assessment(&out_of_range).is_err());
+        let out_of_range = TriageAssessment {
+            pain_score: Some(11),
+            ..Default::default()
+        };
+        assert!(check_assessment(&out_of_range).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Emergency Module
+//!
+//! This module provides emergency department management:
+//! - Arrivals, one open visit per patient at a time
+//! - Triage on the Emergency Severity Index or the Canadian Triage and
+//!   Acuity Scale, as configured
+//! - A tracking board of patients waiting, in treatment and awaiting
+//!   results, flagging those waiting past their level's target
+//! - Door-to-doctor times by triage level
+//! - The urgency of a triaged patient, raised into authorization decisions
+//!   about them while they are in the department
+
+#[path = "emergency.controller.rs"]
+pub mod emergency_controller;
+#[path = "emergency.service.rs"]
+pub mod emergency_service;
+#[path = "emergency.sql.rs"]
+pub mod emergency_sql;
+#[path = "emergency.triage.rs"]
+pub mod emergency_triage;
+#[path = "emergency.metrics.rs"]
+pub mod emergency_metrics;
+
+pub use emergency_controller::EmergencyController;
+pub use emergency_service::{EdVisit, EmergencyConfig, EmergencyService, TriageUrgency};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Emergency Module Configuration
+pub struct EmergencyModule {
+    pub service: Arc<EmergencyService>,
+    pub controller: Arc<EmergencyController>,
+}
+
+impl EmergencyModule {
+    /// Create a new Emergency Module with dependency injection
+    pub fn new(db_pool: PgPool, config: EmergencyConfig) -> Self {
+        let service = Arc::new(EmergencyService::new(db_pool, config));
+        let controller = Arc::new(EmergencyController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<EmergencyService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,141 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Json,
+    },
+};
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::database::tenant::current_tenant;
+use crate::modules::events::events_service::{subject_matches, DomainEvent, EventBus};
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// SSE event name telling the client it fell behind and missed events
+const LAGGED_EVENT: &str = "lagged";
+
+#[derive(Debug, Deserialize)]
+pub struct StreamQuery {
+    /// Comma-separated subject patterns, e.g. `appointment.*,result.received`;
+    /// every event when left out
+    pub subjects: Option<String>,
+}
+
+/// Events controller streaming the tenant's domain events to connected apps
+pub struct EventsController {
+    events: EventBus,
+}
+
+impl EventsController {
+    /// Create new controller with the shared event bus
+    pub fn new(events: EventBus) -> Self {
+        Self { events }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/stream", Self::stream, "Stream domain events as server-sent events")
+            .with_state(self.events.clone())
+    }
+
+    /// Server-sent events for every domain event of the caller's tenant
+    /// published from now on. Each is named by its subject, carries the
+    /// event envelope as JSON and has the envelope ID as its event ID. A
+    /// `lagged` event with the number missed is sent when the client reads
+    /// too slowly to keep up, so it can refetch what it shows.
+    pub async fn stream(
+        State(events): State<EventBus>,
+        headers: HeaderMap,
+        Query(query): Query<StreamQuery>,
+    ) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        let patterns = Self::patterns(query.subjects.as_deref())?;
+        // The stream outlives the request, so it filters by the tenant itself
+        let Some(tenant) = current_tenant() else {
+            return Err(Self::bad_request("No tenant resolved for the request".to_string()));
+        };
+        tracing::info!("Streaming domain events for tenant {}", tenant.tenant_id);
+
+        let receiver = events.subscribe();
+        let stream = stream::unfold(receiver, move |mut receiver| {
+            let patterns = patterns.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(envelope) => {
+                            let subject = envelope.event.subject();
+                            let wanted = patterns.is_empty() || patterns.iter().any(|p| subject_matches(p, &subject));
+                            if envelope.tenant_id != tenant.tenant_id || !wanted {
+                                continue;
+                            }
+                            let event =
+                                Event::default().event(subject).id(envelope.id.to_string()).json_data(&*envelope);
+                            return Some((event, receiver));
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            tracing::warn!("Event stream fell behind and skipped {} events", missed);
+                            return Some((Ok(Event::default().event(LAGGED_EVENT).data(missed.to_string())), receiver));
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+
+        Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+    }
+
+    /// Subject patterns of a stream, each of which must take in some event
+    fn patterns(subjects: Option<&str>) -> Result<Vec<String>, (StatusCode, Json<ErrorResponse>)> {
+        let patterns: Vec<String> = subjects
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect();
+        for pattern in &patterns {
+            if !DomainEvent::SUBJECTS.iter().any(|subject| subject_matches(pattern, subject)) {
+                return Err(Self::bad_request(format!("Unknown event type: {}", pattern)));
+            }
+        }
+        Ok(patterns)
+    }
+
+    /// Get authenticated user from headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn bad_request(message: String) -> (StatusCode, Json<ErrorResponse>) {
+        tracing::warn!("Failed to stream events: {}", message);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Failed to stream events".to_string(),
+                message,
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::modules::events::events_service::{EventBus, EventEnvelope, DEFAULT_EVENT_CAPACITY};
+
+/// Settings for the event bus and the external brokers it forwards to
+#[derive(Debug, Clone)]
+pub struct EventBusConfig {
+    pub capacity: usize,
+    /// `nats://` server to publish to; needs the `nats` feature
+    pub nats_url: Option<String>,
+    /// Comma-separated Kafka bootstrap servers; needs the `kafka` feature
+    pub kafka_brokers: Option<String>,
+    /// Prepended to subjects and topics, as `hims.appointment.cancelled`
+    pub subject_prefix: String,
+}
+
+impl Default for EventBusConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_EVENT_CAPACITY,
+            nats_url: None,
+            kafka_brokers: None,
+            subject_prefix: "hims".to_string(),
+        }
+    }
+}
+
+impl EventBusConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            capacity: std::env::var("EVENT_BUS_CAPACITY")
+                .ok()
+                .and_then(|capacity| capacity.parse().ok())
+                .unwrap_or(defaults.capacity),
+            nats_url: std::env::var("EVENT_BUS_NATS_URL").ok(),
+            kafka_brokers: std::env::var("EVENT_BUS_KAFKA_BROKERS").ok(),
+            subject_prefix: std::env::var("EVENT_BUS_SUBJECT_PREFIX").unwrap_or(defaults.subject_prefix),
+        }
+    }
+
+    /// Subject or topic an event is published under
+    pub fn subject_for(&self, envelope: &EventEnvelope) -> String {
+        format!("{}.{}", self.subject_prefix, envelope.event.subject())
+    }
+
+    /// Connect to every configured broker. A broker configured without its
+    /// feature compiled in is a configuration error rather than silently
+    /// dropped events.
+    pub async fn connect_publishers(&self) -> Result<Vec<Arc<dyn EventPublisher>>, HimsError> {
+        // Only pushed to when a broker feature is compiled in
+        #[allow(unused_mut)]
+        let mut publishers: Vec<Arc<dyn EventPublisher>> = Vec::new();
+
+        if let Some(url) = &self.nats_url {
+            #[cfg(feature = "nats")]
+            publishers.push(Arc::new(NatsPublisher::connect(url, self.clone()).await?));
+            #[cfg(not(feature = "nats"))]
+            return Err(HimsError::ConfigurationError {
+                message: format!("EVENT_BUS_NATS_URL is set to {} but the nats feature is not enabled", url),
+            });
+        }
+        if let Some(brokers) = &self.kafka_brokers {
+            #[cfg(feature = "kafka")]
+            publishers.push(Arc::new(KafkaPublisher::connect(brokers, self.clone())?));
+            #[cfg(not(feature = "kafka"))]
+            return Err(HimsError::ConfigurationError {
+                message: format!("EVENT_BUS_KAFKA_BROKERS is set to {} but the kafka feature is not enabled", brokers),
+            });
+        }
+        Ok(publishers)
+    }
+}
+
+/// Destination outside the process that bus events are copied to
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    /// Name for logs, e.g. `nats`
+    fn name(&self) -> &'static str;
+
+    async fn publish(&self, envelope: &EventEnvelope) -> Result<(), HimsError>;
+}
+
+/// Copy every bus event to a publisher until the bus is dropped. Delivery
+/// is at most once: events that fail to publish, or that the forwarder
+/// falls too far behind to receive, are logged and skipped.
+pub fn forward(bus: &EventBus, publisher: Arc<dyn EventPublisher>) -> JoinHandle<()> {
+    let mut events = bus.subscribe();
+    tokio::spawn(with_tenant(TenantContext::system(), async move {
+        loop {
+            match events.recv().await {
+                Ok(envelope) => {
+                    if let Err(e) = publisher.publish(&envelope).await {
+                        tracing::error!("Failed to publish {} {} to {}: {}", envelope.event.name(), envelope.id, publisher.name(), e);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("{} publisher fell behind and skipped {} events", publisher.name(), missed);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }))
+}
+
+#[cfg(feature = "nats")]
+pub struct NatsPublisher {
+    client: async_nats::Client,
+    config: EventBusConfig,
+}
+
+#[cfg(feature = "nats")]
+impl NatsPublisher {
+    pub async fn connect(url: &str, config: EventBusConfig) -> Result<Self, HimsError> {
+        let client = async_nats::connect(url).await.map_err(|e| HimsError::NetworkError {
+            message: format!("Cannot connect to NATS at {}: {}", url, e),
+        })?;
+        Ok(Self { client, config })
+    }
+}
+
+#[cfg(feature = "nats")]
+#[async_trait]
+impl EventPublisher for NatsPublisher {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    async fn publish(&self, envelope: &EventEnvelope) -> Result<(), HimsError> {
+        let payload = serde_json::to_vec(envelope).map_err(|e| HimsError::InternalError { message: e.to_string() })?;
+        self.client
+            .publish(self.config.subject_for(envelope), payload.into())
+            .await
+            .map_err(|e| HimsError::NetworkError { message: e.to_string() })
+    }
+}
+
+#[cfg(feature = "kafka")]
+pub struct KafkaPublisher {
+    producer: rdkafka::producer::FutureProducer,
+    config: EventBusConfig,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaPublisher {
+    pub fn connect(brokers: &str, config: EventBusConfig) -> Result<Self, HimsError> {
+        let producer = rdkafka::ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", "10000")
+            .create()
+            .map_err(|e| HimsError::ConfigurationError {
+                message: format!("Cannot create Kafka producer for {}: {}", brokers, e),
+            })?;
+        Ok(Self { producer, config })
+    }
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait]
+impl EventPublisher for KafkaPublisher {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    async fn publish(&self, envelope: &EventEnvelope) -> Result<(), HimsError> {
+        let payload = serde_json::to_vec(envelope).map_err(|e| HimsError::InternalError { message: e.to_string() })?;
+        // Keyed by resource, so one resource's events stay in order within a partition
+        let key = envelope.event.resource().1.to_string();
+        let topic = self.config.subject_for(envelope);
+        self.producer
+            .send(
+                rdkafka::producer::FutureRecord::to(&topic).key(&key).payload(&payload),
+                std::time::Duration::from_secs(10),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|(e, _)| HimsError::NetworkError { message: e.to_string() })
+    }
+}
@@ -0,0 +1,320 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::database::provenance::current_change_context;
+use crate::core::HimsError;
+use crate::database::tenant::current_tenant_id;
+use crate::modules::metrics::metrics_service::QueueDepth;
+
+/// Events kept for subscribers that fall behind before they start missing some
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// Something that happened to a resource, published once the write that
+/// caused it has committed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DomainEvent {
+    PatientCreated { patient_id: Uuid },
+    PatientUpdated { patient_id: Uuid },
+    PatientDeleted { patient_id: Uuid },
+    AppointmentBooked { appointment_id: Uuid },
+    AppointmentUpdated { appointment_id: Uuid },
+    AppointmentStatusChanged { appointment_id: Uuid, status: String },
+    AppointmentCancelled { appointment_id: Uuid },
+    AppointmentDeleted { appointment_id: Uuid },
+    RecordCreated { record_id: Uuid, patient_id: Uuid },
+    RecordUpdated { record_id: Uuid },
+    RecordFinalized { record_id: Uuid },
+    RecordAmended { record_id: Uuid, sequence: i32 },
+    RecordDeleted { record_id: Uuid },
+    /// A diagnostic report, such as lab results, was filed for the patient
+    ResultReceived { record_id: Uuid, patient_id: Uuid },
+    ConditionRecorded { condition_id: Uuid, patient_id: Uuid },
+    ConditionUpdated { condition_id: Uuid },
+    ConditionDeleted { condition_id: Uuid },
+    VitalsRecorded { observation_id: Uuid, patient_id: Uuid },
+    VitalsUpdated { observation_id: Uuid },
+    VitalsDeleted { observation_id: Uuid },
+    PlanCreated { care_plan_id: Uuid, patient_id: Uuid },
+    PlanUpdated { care_plan_id: Uuid },
+    PlanDeleted { care_plan_id: Uuid },
+    TaskCreated { task_id: Uuid, patient_id: Uuid },
+    TaskUpdated { task_id: Uuid },
+    TaskAssigned { task_id: Uuid, owner_id: Uuid },
+    TaskDeleted { task_id: Uuid },
+    ReferralCreated { referral_id: Uuid, patient_id: Uuid },
+    ReferralReceived { referral_id: Uuid, patient_id: Uuid },
+    ReferralUpdated { referral_id: Uuid },
+    ReferralAccepted { referral_id: Uuid },
+    ReferralDeclined { referral_id: Uuid },
+    ReferralScheduled { referral_id: Uuid, appointment_id: Uuid },
+    ReferralDeleted { referral_id: Uuid },
+    /// An offline write could not be replayed over the server's version and
+    /// waits for the user to resolve it. `resource_type` is the FHIR type
+    /// of the resource edited offline.
+    ConflictDetected { conflict_id: Uuid, resource_type: String, resource_id: Uuid },
+}
+
+impl DomainEvent {
+    /// [`DomainEvent::subject`] of every event, for consumers that let users
+    /// pick the events they want
+    pub const SUBJECTS: &'static [&'static str] = &[
+        "patient.created",
+        "patient.updated",
+        "patient.deleted",
+        "appointment.booked",
+        "appointment.updated",
+        "appointment.status_changed",
+        "appointment.cancelled",
+        "appointment.deleted",
+        "record.created",
+        "record.updated",
+        "record.finalized",
+        "record.amended",
+        "record.deleted",
+        "result.received",
+        "condition.recorded",
+        "condition.updated",
+        "condition.deleted",
+        "vitals.recorded",
+        "vitals.updated",
+        "vitals.deleted",
+        "plan.created",
+        "plan.updated",
+        "plan.deleted",
+        "task.created",
+        "task.updated",
+        "task.assigned",
+        "task.deleted",
+        "referral.created",
+        "referral.received",
+        "referral.updated",
+        "referral.accepted",
+        "referral.declined",
+        "referral.scheduled",
+        "referral.deleted",
+        "conflict.detected",
+    ];
+
+    /// Variant name, e.g. `AppointmentCancelled`
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainEvent::PatientCreated { .. } => "PatientCreated",
+            DomainEvent::PatientUpdated { .. } => "PatientUpdated",
+            DomainEvent::PatientDeleted { .. } => "PatientDeleted",
+            DomainEvent::AppointmentBooked { .. } => "AppointmentBooked",
+            DomainEvent::AppointmentUpdated { .. } => "AppointmentUpdated",
+            DomainEvent::AppointmentStatusChanged { .. } => "AppointmentStatusChanged",
+            DomainEvent::AppointmentCancelled { .. } => "AppointmentCancelled",
+            DomainEvent::AppointmentDeleted { .. } => "AppointmentDeleted",
+            DomainEvent::RecordCreated { .. } => "RecordCreated",
+            DomainEvent::RecordUpdated { .. } => "RecordUpdated",
+            DomainEvent::RecordFinalized { .. } => "RecordFinalized",
+            DomainEvent::RecordAmended { .. } => "RecordAmended",
+            DomainEvent::RecordDeleted { .. } => "RecordDeleted",
+            DomainEvent::ResultReceived { .. } => "ResultReceived",
+            DomainEvent::ConditionRecorded { .. } => "ConditionRecorded",
+            DomainEvent::ConditionUpdated { .. } => "ConditionUpdated",
+            DomainEvent::ConditionDeleted { .. } => "ConditionDeleted",
+            DomainEvent::VitalsRecorded { .. } => "VitalsRecorded",
+            DomainEvent::VitalsUpdated { .. } => "VitalsUpdated",
+            DomainEvent::VitalsDeleted { .. } => "VitalsDeleted",
+            DomainEvent::PlanCreated { .. } => "PlanCreated",
+            DomainEvent::PlanUpdated { .. } => "PlanUpdated",
+            DomainEvent::PlanDeleted { .. } => "PlanDeleted",
+            DomainEvent::TaskCreated { .. } => "TaskCreated",
+            DomainEvent::TaskUpdated { .. } => "TaskUpdated",
+            DomainEvent::TaskAssigned { .. } => "TaskAssigned",
+            DomainEvent::TaskDeleted { .. } => "TaskDeleted",
+            DomainEvent::ReferralCreated { .. } => "ReferralCreated",
+            DomainEvent::ReferralReceived { .. } => "ReferralReceived",
+            DomainEvent::ReferralUpdated { .. } => "ReferralUpdated",
+            DomainEvent::ReferralAccepted { .. } => "ReferralAccepted",
+            DomainEvent::ReferralDeclined { .. } => "ReferralDeclined",
+            DomainEvent::ReferralScheduled { .. } => "ReferralScheduled",
+            DomainEvent::ReferralDeleted { .. } => "ReferralDeleted",
+            DomainEvent::ConflictDetected { .. } => "ConflictDetected",
+        }
+    }
+
+    /// FHIR type and ID of the resource the event is about
+    pub fn resource(&self) -> (&'static str, Uuid) {
+        match self {
+            DomainEvent::PatientCreated { patient_id }
+            | DomainEvent::PatientUpdated { patient_id }
+            | DomainEvent::PatientDeleted { patient_id } => ("Patient", *patient_id),
+            DomainEvent::AppointmentBooked { appointment_id }
+            | DomainEvent::AppointmentUpdated { appointment_id }
+            | DomainEvent::AppointmentStatusChanged { appointment_id, .. }
+            | DomainEvent::AppointmentCancelled { appointment_id }
+            | DomainEvent::AppointmentDeleted { appointment_id } => ("Appointment", *appointment_id),
+            DomainEvent::RecordCreated { record_id, .. }
+            | DomainEvent::RecordUpdated { record_id }
+            | DomainEvent::RecordFinalized { record_id }
+            | DomainEvent::RecordAmended { record_id, .. }
+            | DomainEvent::RecordDeleted { record_id }
+            | DomainEvent::ResultReceived { record_id, .. } => ("DocumentReference", *record_id),
+            DomainEvent::ConditionRecorded { condition_id, .. }
+            | DomainEvent::ConditionUpdated { condition_id }
+            | DomainEvent::ConditionDeleted { condition_id } => ("Condition", *condition_id),
+            DomainEvent::VitalsRecorded { observation_id, .. }
+            | DomainEvent::VitalsUpdated { observation_id }
+            | DomainEvent::VitalsDeleted { observation_id } => ("Observation", *observation_id),
+            DomainEvent::PlanCreated { care_plan_id, .. }
+            | DomainEvent::PlanUpdated { care_plan_id }
+            | DomainEvent::PlanDeleted { care_plan_id } => ("CarePlan", *care_plan_id),
+            DomainEvent::TaskCreated { task_id, .. }
+            | DomainEvent::TaskUpdated { task_id }
+            | DomainEvent::TaskAssigned { task_id, .. }
+            | DomainEvent::TaskDeleted { task_id } => ("Task", *task_id),
+            DomainEvent::ReferralCreated { referral_id, .. }
+            | DomainEvent::ReferralReceived { referral_id, .. }
+            | DomainEvent::ReferralUpdated { referral_id }
+            | DomainEvent::ReferralAccepted { referral_id }
+            | DomainEvent::ReferralDeclined { referral_id }
+            | DomainEvent::ReferralScheduled { referral_id, .. }
+            | DomainEvent::ReferralDeleted { referral_id } => ("ServiceRequest", *referral_id),
+            DomainEvent::ConflictDetected { resource_type, resource_id, .. } => match resource_type.as_str() {
+                "Patient" => ("Patient", *resource_id),
+                "Appointment" => ("Appointment", *resource_id),
+                _ => ("DocumentReference", *resource_id),
+            },
+        }
+    }
+
+    /// Dotted subject for external brokers, the noun then what happened:
+    /// `appointment.status_changed` for `AppointmentStatusChanged`
+    pub fn subject(&self) -> String {
+        let mut subject = String::new();
+        for (i, c) in self.name().char_indices() {
+            if c.is_uppercase() && i > 0 {
+                subject.push(if subject.contains('.') { '_' } else { '.' });
+            }
+            subject.push(c.to_ascii_lowercase());
+        }
+        subject
+    }
+}
+
+/// Whether a subject pattern, an exact subject, `<noun>.*` or `*`, takes
+/// in events with this subject
+pub fn subject_matches(pattern: &str, subject: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some("") => true,
+        Some(prefix) => prefix.ends_with('.') && subject.starts_with(prefix),
+        None => pattern == subject,
+    }
+}
+
+/// An event with where and when it happened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    /// User whose request caused the event, when known
+    pub actor: Option<Uuid>,
+    #[serde(flatten)]
+    pub event: DomainEvent,
+}
+
+impl EventEnvelope {
+    /// Wrap an event with the current task's tenant and user
+    pub fn new(event: DomainEvent) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id: current_tenant_id(),
+            occurred_at: Utc::now(),
+            actor: current_change_context().and_then(|context| context.user_id),
+            event,
+        }
+    }
+}
+
+/// In-process publish/subscribe for domain events. Publishing never waits:
+/// each subscriber has its own bounded backlog, and one that falls further
+/// behind misses the oldest events rather than slowing writes down.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<EventEnvelope>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Publish an event to everyone subscribed now. Returns how many
+    /// subscribers it reached.
+    pub fn publish(&self, event: DomainEvent) -> usize {
+        let envelope = Arc::new(EventEnvelope::new(event));
+        tracing::debug!(event = envelope.event.name(), id = %envelope.id, "Publishing domain event");
+        // No subscribers is not an error; nobody is interested yet
+        self.sender.send(envelope).unwrap_or(0)
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<EventEnvelope>> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+#[async_trait]
+impl QueueDepth for EventBus {
+    /// Events published but not yet read by every subscriber, such as the
+    /// broker forwarders and webhook listener: the bus's outbox
+    async fn depth(&self) -> Result<i64, HimsError> {
+        Ok(self.sender.len() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_receive_published_events() {
+        let bus = EventBus::new(8);
+        assert_eq!(bus.publish(DomainEvent::PatientDeleted { patient_id: Uuid::new_v4() }), 0);
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        let appointment_id = Uuid::new_v4();
+        assert_eq!(bus.publish(DomainEvent::AppointmentCancelled { appointment_id }), 2);
+
+        let received = first.recv().await.unwrap();
+        assert_eq!(received.event, DomainEvent::AppointmentCancelled { appointment_id });
+        assert_eq!(second.recv().await.unwrap().id, received.id);
+    }
+
+    #[test]
+    fn test_event_naming() {
+        let event = DomainEvent::AppointmentStatusChanged {
+            appointment_id: Uuid::new_v4(),
+            status: "arrived".to_string(),
+        };
+        assert_eq!(event.subject(), "appointment.status_changed");
+        assert_eq!(DomainEvent::RecordFinalized { record_id: Uuid::nil() }.subject(), "record.finalized");
+        assert_eq!(event.resource().0, "Appointment");
+        assert!(DomainEvent::SUBJECTS.contains(&event.subject().as_str()));
+
+        let json = serde_json::to_value(EventEnvelope::new(event)).unwrap();
+        assert_eq!(json["type"], "AppointmentStatusChanged");
+        assert_eq!(json["status"], "arrived");
+    }
+}
@@ -0,0 +1,18 @@
+//! Events Module
+//!
+//! This module decouples modules that react to writes from the modules making them including:
+//! - Domain events (`PatientCreated`, `AppointmentCancelled`, `RecordFinalized`, ...) published after commit
+//! - An in-process bus on tokio broadcast that any number of consumers subscribe to
+//! - Optional forwarding to NATS (`nats` feature) or Kafka (`kafka` feature)
+//! - A server-sent events stream of the tenant's events for connected apps
+
+#[path = "events.service.rs"]
+pub mod events_service;
+#[path = "events.publisher.rs"]
+pub mod events_publisher;
+#[path = "events.controller.rs"]
+pub mod events_controller;
+
+pub use events_service::{subject_matches, DomainEvent, EventBus, EventEnvelope};
+pub use events_controller::EventsController;
+pub use events_publisher::{forward, EventBusConfig, EventPublisher};
@@ -0,0 +1,92 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::modules::auth::AuthContext;
+use crate::modules::field_filter::FieldFilterService;
+
+/// Response interceptor applying minimum-necessary response profiles
+pub struct FieldFilterMiddleware {
+    service: Arc<FieldFilterService>,
+}
+
+impl FieldFilterMiddleware {
+    pub fn new(service: Arc<FieldFilterService>) -> Self {
+        Self { service }
+    }
+
+    pub fn service(&self) -> Arc<FieldFilterService> {
+        self.service.clone()
+    }
+
+    /// Filter the JSON responses of users whose roles have a profile. A
+    /// single resource the profile withholds is a 403; withheld entries of
+    /// bundles and lists are dropped. Runs inside `AuthContext::attach`;
+    /// anonymous requests and other bodies pass through.
+    pub async fn filter(State(service): State<Arc<FieldFilterService>>, request: Request, next: Next) -> Response {
+        let Some(user_id) = request.extensions().get::<AuthContext>().map(|auth| auth.user_id) else {
+            return next.run(request).await;
+        };
+        let view = match service.view(user_id).await {
+            Ok(Some(view)) => view,
+            Ok(None) => return next.run(request).await,
+            Err(e) => {
+                // Serving the unfiltered response would disclose what the profile withholds
+                tracing::error!("Failed to resolve response profile of user {}: {}", user_id, e);
+                return Self::error(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Response filtering unavailable",
+                    "The response could not be limited to your role; retry later".to_string(),
+                );
+            }
+        };
+
+        let response = next.run(request).await;
+        if !response.status().is_success() || !Self::is_json(&response) {
+            return response;
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let bytes = match to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::error!("Failed to read response to filter: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+            return Response::from_parts(parts, Body::from(bytes));
+        };
+
+        if !view.filter(&mut value) {
+            tracing::warn!("Withheld {} from user {} under profile {}", value["resourceType"], user_id, view.role.name);
+            return Self::error(
+                StatusCode::FORBIDDEN,
+                "Access denied",
+                format!("{} is beyond the minimum necessary for your role", value["resourceType"]),
+            );
+        }
+        parts.headers.remove(header::CONTENT_LENGTH);
+        Response::from_parts(parts, Body::from(value.to_string()))
+    }
+
+    /// JSON or FHIR JSON bodies
+    fn is_json(response: &Response) -> bool {
+        response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json") || value.starts_with("application/fhir+json"))
+    }
+
+    fn error(status: StatusCode, error: &str, message: String) -> Response {
+        (status, Json(json!({ "error": error, "message": message }))).into_response()
+    }
+}
@@ -0,0 +1,369 @@
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+use crate::models::constants::{BUNDLE_RESOURCE_TYPE, OBSERVATION_VALUE_SYSTEM, REDACTED_SECURITY_CODE};
+use crate::modules::authorization::HealthcareRelation;
+
+/// Elements every served resource keeps, so clients can still tell what it is
+const IDENTIFYING_ELEMENTS: [&str; 3] = ["resourceType", "id", "meta"];
+
+/// Insurance and financial resources billing works from
+const FINANCIAL_RESOURCES: [&str; 9] = [
+    "Account",
+    "ChargeItem",
+    "Claim",
+    "ClaimResponse",
+    "Coverage",
+    "CoverageEligibilityRequest",
+    "CoverageEligibilityResponse",
+    "ExplanationOfBenefit",
+    "Invoice",
+];
+
+/// Elements of one resource type a profile shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldRule {
+    /// Every element
+    All,
+    /// Only these elements
+    Only(BTreeSet<String>),
+    /// Every element but these
+    Except(BTreeSet<String>),
+    /// None; the resource is withheld
+    Hidden,
+}
+
+impl FieldRule {
+    pub fn only(elements: &[&str]) -> Self {
+        FieldRule::Only(elements.iter().map(|element| element.to_string()).collect())
+    }
+
+    pub fn except(elements: &[&str]) -> Self {
+        FieldRule::Except(elements.iter().map(|element| element.to_string()).collect())
+    }
+
+    /// Rule showing every element either rule shows
+    pub fn union(&self, other: &FieldRule) -> FieldRule {
+        match (self, other) {
+            (FieldRule::All, _) | (_, FieldRule::All) => FieldRule::All,
+            (FieldRule::Hidden, rule) | (rule, FieldRule::Hidden) => rule.clone(),
+            (FieldRule::Only(a), FieldRule::Only(b)) => FieldRule::Only(a.union(b).cloned().collect()),
+            (FieldRule::Except(a), FieldRule::Except(b)) => FieldRule::Except(a.intersection(b).cloned().collect()),
+            (FieldRule::Only(shown), FieldRule::Except(hidden))
+            | (FieldRule::Except(hidden), FieldRule::Only(shown)) => {
+                FieldRule::Except(hidden.difference(shown).cloned().collect())
+            }
+        }
+    }
+
+    fn shows(&self, element: &str) -> bool {
+        IDENTIFYING_ELEMENTS.contains(&element)
+            || match self {
+                FieldRule::All => true,
+                FieldRule::Only(shown) => shown.contains(element),
+                FieldRule::Except(hidden) => !hidden.contains(element),
+                FieldRule::Hidden => false,
+            }
+    }
+}
+
+/// The minimum necessary a role or relationship sees of responses: a rule
+/// per resource type, and one for the types it does not list
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseProfile {
+    pub name: String,
+    pub rules: HashMap<String, FieldRule>,
+    /// Rule of resource types not in `rules`
+    pub otherwise: FieldRule,
+}
+
+impl ResponseProfile {
+    fn new(name: &str, rules: Vec<(&str, FieldRule)>, otherwise: FieldRule) -> Self {
+        Self {
+            name: name.to_string(),
+            rules: rules.into_iter().map(|(resource_type, rule)| (resource_type.to_string(), rule)).collect(),
+            otherwise,
+        }
+    }
+
+    /// Everything; the view of treating clinicians
+    pub fn full() -> Self {
+        Self::new("full", Vec::new(), FieldRule::All)
+    }
+
+    /// Demographics, visits and insurance, but no clinical content
+    pub fn billing() -> Self {
+        let mut rules = vec![
+            (
+                "Patient",
+                FieldRule::only(&["identifier", "active", "name", "telecom", "gender", "birthDate", "address"]),
+            ),
+            (
+                "Encounter",
+                FieldRule::only(&["identifier", "status", "class", "type", "subject", "period", "serviceProvider"]),
+            ),
+            ("Appointment", FieldRule::only(&["identifier", "status", "serviceType", "start", "end", "participant"])),
+        ];
+        rules.extend(FINANCIAL_RESOURCES.iter().map(|resource_type| (*resource_type, FieldRule::All)));
+        Self::new("billing", rules, FieldRule::Hidden)
+    }
+
+    /// Demographics and scheduling, with enough of coverage to register a
+    /// visit
+    pub fn front_desk() -> Self {
+        Self::new(
+            "front_desk",
+            vec![
+                ("Patient", FieldRule::All),
+                ("Appointment", FieldRule::All),
+                ("Schedule", FieldRule::All),
+                ("Slot", FieldRule::All),
+                (
+                    "Encounter",
+                    FieldRule::only(&["identifier", "status", "class", "subject", "period", "location"]),
+                ),
+                ("Coverage", FieldRule::only(&["identifier", "status", "beneficiary", "payor", "period"])),
+            ],
+            FieldRule::Hidden,
+        )
+    }
+
+    /// Clinical content without the patient's direct identifiers
+    pub fn research() -> Self {
+        Self::new(
+            "research",
+            vec![(
+                "Patient",
+                FieldRule::except(&["identifier", "name", "telecom", "address", "contact", "photo", "text"]),
+            )],
+            FieldRule::except(&["identifier", "text"]),
+        )
+    }
+
+    /// Everything but the patient's contact details
+    pub fn restricted() -> Self {
+        Self::new(
+            "restricted",
+            vec![("Patient", FieldRule::except(&["telecom", "address", "contact", "photo"]))],
+            FieldRule::All,
+        )
+    }
+
+    /// Built-in profile by name
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "full" => Some(Self::full()),
+            "billing" => Some(Self::billing()),
+            "front_desk" => Some(Self::front_desk()),
+            "research" => Some(Self::research()),
+            "restricted" => Some(Self::restricted()),
+            _ => None,
+        }
+    }
+
+    /// Profile a relationship to a patient grants: treating the patient
+    /// shows everything, billing or research access their profiles
+    pub fn for_relation(relation: &HealthcareRelation) -> Option<Self> {
+        match relation {
+            HealthcareRelation::PrimaryPhysician
+            | HealthcareRelation::ConsultingPhysician
+            | HealthcareRelation::SpecialistReferral
+            | HealthcareRelation::AttendingNurse
+            | HealthcareRelation::CareTeamMember
+            | HealthcareRelation::TreatingPhysician
+            | HealthcareRelation::OrderingPhysician
+            | HealthcareRelation::SupervisingPhysician
+            | HealthcareRelation::ConsultingSpecialist
+            | HealthcareRelation::SecondOpinion => Some(Self::full()),
+            HealthcareRelation::BillingAccess => Some(Self::billing()),
+            HealthcareRelation::ResearchAccess => Some(Self::research()),
+            _ => None,
+        }
+    }
+
+    pub fn rule(&self, resource_type: &str) -> &FieldRule {
+        self.rules.get(resource_type).unwrap_or(&self.otherwise)
+    }
+
+    /// Profile showing everything either profile shows
+    pub fn union(&self, other: &ResponseProfile) -> ResponseProfile {
+        let rules = self
+            .rules
+            .keys()
+            .chain(other.rules.keys())
+            .map(|resource_type| {
+                (resource_type.clone(), self.rule(resource_type).union(other.rule(resource_type)))
+            })
+            .collect();
+        ResponseProfile {
+            name: format!("{}+{}", self.name, other.name),
+            rules,
+            otherwise: self.otherwise.union(&other.otherwise),
+        }
+    }
+
+    /// Drop the elements of a resource this profile does not show, labelling
+    /// it redacted if any were. False when the whole resource is withheld.
+    pub fn filter_resource(&self, resource: &mut Value) -> bool {
+        let Some(object) = resource.as_object_mut() else {
+            return true;
+        };
+        let Some(resource_type) = object.get("resourceType").and_then(Value::as_str) else {
+            return true;
+        };
+        let rule = self.rule(resource_type).clone();
+        if rule == FieldRule::Hidden {
+            return false;
+        }
+
+        let elements = object.len();
+        object.retain(|element, _| rule.shows(element));
+        if object.len() < elements {
+            let meta = object.entry("meta").or_insert_with(|| json!({}));
+            if let Some(meta) = meta.as_object_mut() {
+                let security = meta.entry("security").or_insert_with(|| json!([]));
+                if let Some(security) = security.as_array_mut() {
+                    security.push(json!({ "system": OBSERVATION_VALUE_SYSTEM, "code": REDACTED_SECURITY_CODE }));
+                }
+            }
+        }
+        true
+    }
+}
+
+/// A user's view of responses: the profile of their roles, widened per
+/// patient by their relationships to the patient
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseView {
+    pub role: ResponseProfile,
+    pub patients: HashMap<Uuid, ResponseProfile>,
+}
+
+impl ResponseView {
+    /// Profile of a resource, by the patient it is about
+    pub fn profile_for(&self, resource: &Value) -> &ResponseProfile {
+        Self::patient_of(resource)
+            .and_then(|patient_id| self.patients.get(&patient_id))
+            .unwrap_or(&self.role)
+    }
+
+    /// Filter a response body: a resource, a Bundle of them or a list.
+    /// Withheld entries and items are dropped; false when the body is a
+    /// single resource that is withheld.
+    pub fn filter(&self, body: &mut Value) -> bool {
+        match body {
+            Value::Array(items) => {
+                items.retain_mut(|item| self.filter(item));
+                true
+            }
+            Value::Object(object) if Self::is_bundle(object) => {
+                if let Some(entries) = object.get_mut("entry").and_then(Value::as_array_mut) {
+                    entries.retain_mut(|entry| match entry.get_mut("resource") {
+                        Some(resource) => self.filter(resource),
+                        None => true,
+                    });
+                }
+                true
+            }
+            _ => {
+                let profile = self.profile_for(body).clone();
+                profile.filter_resource(body)
+            }
+        }
+    }
+
+    fn is_bundle(object: &serde_json::Map<String, Value>) -> bool {
+        object.get("resourceType").and_then(Value::as_str) == Some(BUNDLE_RESOURCE_TYPE)
+    }
+
+    /// Patient a resource is, or is about
+    fn patient_of(resource: &Value) -> Option<Uuid> {
+        if resource.get("resourceType").and_then(Value::as_str) == Some("Patient") {
+            return resource.get("id")?.as_str()?.parse().ok();
+        }
+        ["subject", "patient", "beneficiary"].iter().find_map(|element| {
+            resource
+                .get(element)?
+                .get("reference")?
+                .as_str()?
+                .strip_prefix("Patient/")?
+                .parse()
+                .ok()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_billing_profile() {
+        let profile = ResponseProfile::billing();
+
+        let mut patient = json!({
+            "resourceType": "Patient",
+            "id": "p1",
+            "name": [{ "family": "Shah" }],
+            "address": [{ "city": "Pune" }],
+            "maritalStatus": { "text": "Married" },
+        });
+        assert!(profile.filter_resource(&mut patient));
+        assert_eq!(patient["name"][0]["family"], "Shah");
+        assert!(patient.get("maritalStatus").is_none());
+        assert_eq!(patient["meta"]["security"][0]["code"], REDACTED_SECURITY_CODE);
+
+        let mut coverage = json!({ "resourceType": "Coverage", "id": "c1", "payor": [{ "display": "PMJAY" }] });
+        assert!(profile.filter_resource(&mut coverage));
+        assert!(coverage.get("meta").is_none());
+
+        let mut note = json!({ "resourceType": "DocumentReference", "id": "d1", "content": [] });
+        assert!(!profile.filter_resource(&mut note));
+    }
+
+    #[test]
+    fn test_union() {
+        let profile = ResponseProfile::billing().union(&ResponseProfile::front_desk());
+        assert_eq!(profile.rule("Patient"), &FieldRule::All);
+        assert_eq!(profile.rule("Coverage"), &FieldRule::All);
+        assert_eq!(profile.rule("Observation"), &FieldRule::Hidden);
+
+        let rule = FieldRule::only(&["name", "gender"]).union(&FieldRule::except(&["name", "telecom"]));
+        assert_eq!(rule, FieldRule::except(&["telecom"]));
+        assert_eq!(ResponseProfile::full().union(&ResponseProfile::billing()).rule("Observation"), &FieldRule::All);
+    }
+
+    #[test]
+    fn test_view_filters_bundle_by_patient() {
+        let treated = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let view = ResponseView {
+            role: ResponseProfile::billing(),
+            patients: HashMap::from([(treated, ResponseProfile::billing().union(&ResponseProfile::full()))]),
+        };
+
+        let about = |patient: Uuid| json!({ "reference": format!("Patient/{}", patient) });
+        let mut bundle = json!({
+            "resourceType": "Bundle",
+            "entry": [
+                { "resource": { "resourceType": "Observation", "id": "o1", "subject": about(treated) } },
+                { "resource": { "resourceType": "Observation", "id": "o2", "subject": about(other) } },
+                { "resource": { "resourceType": "Claim", "id": "c1", "patient": about(other) } },
+            ],
+        });
+        assert!(view.filter(&mut bundle));
+        let ids: Vec<&str> = bundle["entry"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["resource"]["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["o1", "c1"]);
+
+        let mut observation = json!({ "resourceType": "Observation", "id": "o3" });
+        assert!(!view.filter(&mut observation));
+        let mut error = json!({ "error": "Not found", "message": "No such record" });
+        assert!(view.filter(&mut error));
+    }
+}
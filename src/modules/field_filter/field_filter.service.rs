@@ -0,0 +1,116 @@
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::{AuthorizationStorage, PostgresAuthorizationStorage, Resource, Subject};
+use crate::modules::field_filter::field_filter_profile::{ResponseProfile, ResponseView};
+use crate::modules::role::RoleService;
+
+/// Roles given a profile unless `RESPONSE_PROFILE_ROLES` says otherwise
+const DEFAULT_ROLE_PROFILES: [(&str, &str); 2] = [("billing_clerk", "billing"), ("receptionist", "front_desk")];
+
+#[derive(Debug, Clone)]
+pub struct FieldFilterConfig {
+    /// Response profile of each role, by role name. Users holding none of
+    /// these roles see responses unfiltered.
+    pub role_profiles: HashMap<String, String>,
+}
+
+impl Default for FieldFilterConfig {
+    fn default() -> Self {
+        Self {
+            role_profiles: DEFAULT_ROLE_PROFILES
+                .iter()
+                .map(|(role, profile)| (role.to_string(), profile.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl FieldFilterConfig {
+    /// `RESPONSE_PROFILE_ROLES` as `role=profile` pairs separated by commas,
+    /// e.g. `billing_clerk=billing,research_associate=research`
+    pub fn from_env() -> Self {
+        match std::env::var("RESPONSE_PROFILE_ROLES") {
+            Ok(value) => Self {
+                role_profiles: value
+                    .split(',')
+                    .filter_map(|pair| pair.split_once('='))
+                    .map(|(role, profile)| (role.trim().to_string(), profile.trim().to_string()))
+                    .collect(),
+            },
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Resolves the response profiles limiting what each user sees to the
+/// minimum necessary for their role and relationships
+pub struct FieldFilterService {
+    role_service: RoleService,
+    storage: PostgresAuthorizationStorage,
+    role_profiles: HashMap<String, ResponseProfile>,
+}
+
+impl FieldFilterService {
+    pub fn new(pool: PgPool, config: FieldFilterConfig) -> Self {
+        let role_profiles = config
+            .role_profiles
+            .into_iter()
+            .filter_map(|(role, name)| match ResponseProfile::named(&name) {
+                Some(profile) => Some((role, profile)),
+                None => {
+                    tracing::warn!("Ignoring unknown response profile {} of role {}", name, role);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            storage: PostgresAuthorizationStorage::new(pool),
+            role_profiles,
+        }
+    }
+
+    /// A user's view of responses, or `None` when none of their roles has a
+    /// profile and they see everything
+    pub async fn view(&self, user_id: Uuid) -> Result<Option<ResponseView>, HimsError> {
+        let Some(role) = self.role_profile(user_id).await? else {
+            return Ok(None);
+        };
+
+        let relationships = self
+            .storage
+            .get_relationships_for_subject(&Subject::User(user_id))
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let mut patients: HashMap<Uuid, ResponseProfile> = HashMap::new();
+        for tuple in relationships {
+            let (Resource::Patient(patient_id), Some(profile)) =
+                (&tuple.object, ResponseProfile::for_relation(&tuple.relation))
+            else {
+                continue;
+            };
+            let widened = patients.get(patient_id).unwrap_or(&role).union(&profile);
+            patients.insert(*patient_id, widened);
+        }
+
+        Ok(Some(ResponseView { role, patients }))
+    }
+
+    /// Union of the profiles of the user's roles
+    async fn role_profile(&self, user_id: Uuid) -> Result<Option<ResponseProfile>, HimsError> {
+        let roles = self.role_service.get_user_role_names(user_id).await?;
+        Ok(roles
+            .iter()
+            .filter_map(|role| self.role_profiles.get(role))
+            .fold(None, |view: Option<ResponseProfile>, profile| {
+                Some(match view {
+                    Some(view) => view.union(profile),
+                    None => profile.clone(),
+                })
+            }))
+    }
+}
@@ -0,0 +1,42 @@
+//! Field Filter Module
+//!
+//! This module limits responses to the minimum necessary including:
+//! - Response profiles per resource type, e.g. billing sees insurance but no clinical notes
+//! - Profiles by role name, widened per patient by relationships such as treating or billing access
+//! - A response interceptor applying the profiles to every controller's JSON responses
+//! - `REDACTED` security labels on resources served with elements withheld
+
+#[path = "field_filter.profile.rs"]
+pub mod field_filter_profile;
+#[path = "field_filter.service.rs"]
+pub mod field_filter_service;
+#[path = "field_filter.middleware.rs"]
+pub mod field_filter_middleware;
+
+pub use field_filter_middleware::FieldFilterMiddleware;
+pub use field_filter_profile::{FieldRule, ResponseProfile, ResponseView};
+pub use field_filter_service::{FieldFilterConfig, FieldFilterService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Field Filter Module Configuration
+pub struct FieldFilterModule {
+    pub service: Arc<FieldFilterService>,
+    pub middleware: Arc<FieldFilterMiddleware>,
+}
+
+impl FieldFilterModule {
+    /// Create a new Field Filter Module with dependency injection
+    pub fn new(db_pool: PgPool, config: FieldFilterConfig) -> Self {
+        let service = Arc::new(FieldFilterService::new(db_pool, config));
+        let middleware = Arc::new(FieldFilterMiddleware::new(service.clone()));
+
+        Self { service, middleware }
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<FieldFilterService> {
+        self.service.clone()
+    }
+}
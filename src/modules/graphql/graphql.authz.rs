@@ -0,0 +1,205 @@
+use async_graphql::{Error, ErrorExtensions};
+use axum::http::HeaderMap;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{
+    AccessDecision, Action, AuthorizationEngine, AuthorizationRequest, AuthorizationResponse, ConfidentialityLabel,
+    Consistency, HimsAuthorizationEngine, RequestContext, Resource, SessionContext, Subject,
+};
+use crate::utils::auth::get_user_session_context;
+
+/// Authorization decisions for one GraphQL request. Each (action, resource)
+/// pair is checked once however many fields need it, and lists are checked
+/// with a single batch call.
+pub struct FieldAuthorizer {
+    engine: Arc<HimsAuthorizationEngine>,
+    user_id: Uuid,
+    context: RequestContext,
+    session: SessionContext,
+    decisions: Mutex<HashMap<(Action, Resource), bool>>,
+}
+
+impl FieldAuthorizer {
+    /// Authorizer for a request, acting in the session the `AuthContext`
+    /// extractor resolved, from its source IP and for the purpose of use it
+    /// declares. None of these are read from headers a client could set.
+    pub fn for_request(engine: Arc<HimsAuthorizationEngine>, auth: &AuthContext, mut context: RequestContext) -> Self {
+        context.purpose_of_use = auth.purpose_of_use;
+        let session = SessionContext {
+            user_id: auth.user_id,
+            session_id: auth.session_id.clone().unwrap_or_else(|| "unknown".to_string()),
+            ip_address: auth.source_ip.clone(),
+            user_agent: auth.user_agent.clone(),
+            department_id: None,
+            location_id: None,
+            shift_id: None,
+            mfa_verified: false,
+            mfa_verified_at: None,
+            risk_score: context.risk_score,
+        };
+
+        Self::with_session(engine, context, session)
+    }
+
+    /// [`Self::for_request`] with the session context the request's headers
+    /// describe, for REST handlers
+    pub async fn for_headers(
+        engine: Arc<HimsAuthorizationEngine>,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+    ) -> Result<Self, HimsError> {
+        let context = get_user_session_context(auth, headers)
+            .await
+            .map_err(|e| HimsError::InternalError {
+                message: format!("Failed to establish session context: {}", e),
+            })?;
+        Ok(Self::for_request(engine, auth, context))
+    }
+
+    fn with_session(engine: Arc<HimsAuthorizationEngine>, context: RequestContext, session: SessionContext) -> Self {
+        Self {
+            engine,
+            user_id: session.user_id,
+            context,
+            session,
+            decisions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Engine request for the user of this authorizer
+    pub fn request(&self, action: Action, resource: Resource) -> AuthorizationRequest {
+        AuthorizationRequest {
+            subject: Subject::User(self.user_id),
+            action,
+            resource,
+            context: self.context.clone(),
+            session: self.session.clone(),
+            request_id: Some(Uuid::new_v4().to_string()),
+            consistency: Consistency::default(),
+        }
+    }
+
+    pub fn permits(response: &AuthorizationResponse) -> bool {
+        match response.decision {
+            AccessDecision::Allow
+            | AccessDecision::AllowWithRestrictions
+            | AccessDecision::EmergencyAccess
+            | AccessDecision::BreakGlassAccess => true,
+            AccessDecision::Deny | AccessDecision::RequireApproval | AccessDecision::RequireMFA => false,
+        }
+    }
+
+    fn decided(&self, action: &Action, resource: &Resource) -> Option<bool> {
+        let decisions = self.decisions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        decisions.get(&(action.clone(), resource.clone())).copied()
+    }
+
+    fn remember(&self, action: Action, resource: Resource, allowed: bool) {
+        let mut decisions = self.decisions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        decisions.insert((action, resource), allowed);
+    }
+
+    pub async fn allowed(&self, action: Action, resource: Resource) -> Result<bool, HimsError> {
+        if let Some(allowed) = self.decided(&action, &resource) {
+            return Ok(allowed);
+        }
+
+        let response = self
+            .engine
+            .check(self.request(action.clone(), resource.clone()))
+            .await
+            .map_err(|e| HimsError::InternalError {
+                message: format!("Authorization check failed: {}", e),
+            })?;
+        let allowed = Self::permits(&response);
+        self.remember(action, resource, allowed);
+        Ok(allowed)
+    }
+
+    /// Fail with a security error unless the action is allowed on the
+    /// resource, for services and REST handlers
+    pub async fn authorize(&self, action: Action, resource: Resource) -> Result<(), HimsError> {
+        if self.allowed(action.clone(), resource.clone()).await? {
+            Ok(())
+        } else {
+            tracing::warn!("Access denied: {:?} on {:?} for user {}", action, resource, self.user_id);
+            Err(HimsError::SecurityError {
+                message: format!("{:?} on {:?} is not permitted", action, resource),
+            })
+        }
+    }
+
+    /// Fail the field unless the action is allowed on the resource
+    pub async fn require(&self, action: Action, resource: Resource) -> async_graphql::Result<()> {
+        if self.allowed(action.clone(), resource.clone()).await? {
+            Ok(())
+        } else {
+            Err(Error::new(format!("Access denied: {:?} on {:?}", action, resource))
+                .extend_with(|_, extensions| extensions.set("code", "FORBIDDEN")))
+        }
+    }
+
+    /// The items the action is allowed on; the others are left out, as a
+    /// search leaves out what does not match
+    pub async fn retain<T>(&self, action: Action, items: Vec<T>, resource: impl Fn(&T) -> Resource) -> Result<Vec<T>, HimsError> {
+        let mut undecided: Vec<Resource> = Vec::new();
+        for item in &items {
+            let resource = resource(item);
+            if self.decided(&action, &resource).is_none() && !undecided.contains(&resource) {
+                undecided.push(resource);
+            }
+        }
+
+        if !undecided.is_empty() {
+            let requests = undecided.iter().map(|resource| self.request(action.clone(), resource.clone())).collect();
+            let responses = self.engine.check_batch(requests).await.map_err(|e| HimsError::InternalError {
+                message: format!("Authorization check failed: {}", e),
+            })?;
+            for (resource, response) in undecided.into_iter().zip(responses) {
+                self.remember(action.clone(), resource, Self::permits(&response));
+            }
+        }
+
+        Ok(items
+            .into_iter()
+            .filter(|item| self.decided(&action, &resource(item)).unwrap_or(false))
+            .collect())
+    }
+
+    /// The items the action is allowed on, each checked as carrying the
+    /// labels `labels` gives it besides those recorded on its resource, e.g.
+    /// versions of a record labelled since relabelled. Not cached, as the
+    /// same resource may be asked about with different labels.
+    pub async fn retain_labelled<T>(
+        &self,
+        action: Action,
+        items: Vec<T>,
+        resource: impl Fn(&T) -> Resource,
+        labels: impl Fn(&T) -> Vec<ConfidentialityLabel>,
+    ) -> Result<Vec<T>, HimsError> {
+        if items.is_empty() {
+            return Ok(items);
+        }
+        let requests = items
+            .iter()
+            .map(|item| {
+                let mut request = self.request(action.clone(), resource(item));
+                request.context = std::mem::take(&mut request.context).with_security_labels(labels(item));
+                request
+            })
+            .collect();
+        let responses = self.engine.check_batch(requests).await.map_err(|e| HimsError::InternalError {
+            message: format!("Authorization check failed: {}", e),
+        })?;
+        Ok(items
+            .into_iter()
+            .zip(responses)
+            .filter(|(_, response)| Self::permits(response))
+            .map(|(item, _)| item)
+            .collect())
+    }
+}
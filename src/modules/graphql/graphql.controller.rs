@@ -0,0 +1,170 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::graphql::graphql_service::{GraphqlRequest, PersistedQuery, PersistedQueryRequest};
+use crate::modules::graphql::GraphqlService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// GraphQL controller for query execution and the persisted query allowlist
+pub struct GraphqlController {
+    graphql_service: Arc<GraphqlService>,
+}
+
+impl GraphqlController {
+    /// Create new controller with injected service
+    pub fn new(graphql_service: Arc<GraphqlService>) -> Self {
+        Self { graphql_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::execute, "Execute GraphQL query")
+            .get("/schema", Self::schema, "Get GraphQL schema in SDL")
+            .post("/persisted-queries", Self::register_persisted_query, "Add query to persisted query allowlist")
+            .get("/persisted-queries", Self::list_persisted_queries, "List persisted queries")
+            .delete("/persisted-queries/:hash", Self::delete_persisted_query, "Remove query from persisted query allowlist")
+            .with_state(self.graphql_service.clone())
+    }
+
+    /// Execute a query, or a persisted query named by its hash
+    pub async fn execute(
+        State(graphql_service): State<Arc<GraphqlService>>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Json(payload): Json<GraphqlRequest>,
+    ) -> Result<Json<async_graphql::Response>, (StatusCode, Json<ErrorResponse>)> {
+        match graphql_service.execute(&auth, &headers, payload).await {
+            Ok(response) => Ok(Json(response)),
+            Err(e) => Err(Self::error_response("Failed to execute GraphQL query", e)),
+        }
+    }
+
+    /// Schema in SDL
+    pub async fn schema(
+        State(graphql_service): State<Arc<GraphqlService>>,
+        headers: HeaderMap,
+    ) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        Ok(graphql_service.sdl())
+    }
+
+    /// Add a query to the allowlist
+    pub async fn register_persisted_query(
+        State(graphql_service): State<Arc<GraphqlService>>,
+        headers: HeaderMap,
+        Json(payload): Json<PersistedQueryRequest>,
+    ) -> Result<(StatusCode, Json<PersistedQuery>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::manager(&graphql_service, &headers)?;
+        tracing::info!("Registering persisted GraphQL query {}", payload.name);
+
+        match graphql_service.register_persisted_query(actor, payload).await {
+            Ok(query) => Ok((StatusCode::CREATED, Json(query))),
+            Err(e) => Err(Self::error_response("Failed to register persisted query", e)),
+        }
+    }
+
+    /// List the allowlist
+    pub async fn list_persisted_queries(
+        State(graphql_service): State<Arc<GraphqlService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<PersistedQuery>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::manager(&graphql_service, &headers)?;
+
+        match graphql_service.list_persisted_queries().await {
+            Ok(queries) => Ok(Json(queries)),
+            Err(e) => Err(Self::error_response("Failed to list persisted queries", e)),
+        }
+    }
+
+    /// Remove a query from the allowlist
+    pub async fn delete_persisted_query(
+        State(graphql_service): State<Arc<GraphqlService>>,
+        headers: HeaderMap,
+        Path(hash): Path<String>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        Self::manager(&graphql_service, &headers)?;
+        tracing::info!("Deleting persisted GraphQL query: {}", hash);
+
+        match graphql_service.delete_persisted_query(&hash).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found(&hash)),
+            Err(e) => Err(Self::error_response("Failed to delete persisted query", e)),
+        }
+    }
+
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Callers limited to persisted queries cannot change the allowlist
+    fn manager(graphql_service: &GraphqlService, headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(headers)?;
+        if graphql_service.is_persisted_only(headers) {
+            return Err(Self::error_response(
+                "Failed to manage persisted queries",
+                HimsError::SecurityError {
+                    message: "Persisted query clients cannot manage the allowlist".to_string(),
+                },
+            ));
+        }
+        Ok(actor)
+    }
+
+    fn not_found(hash: &str) -> (StatusCode, Json<ErrorResponse>) {
+        tracing::warn!("Persisted query not found: {}", hash);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Persisted query not found".to_string(),
+                message: format!("Persisted query with hash {} not found", hash),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        if status == StatusCode::FORBIDDEN {
+            tracing::warn!("{}: {}", context, e);
+        } else {
+            tracing::error!("{}: {}", context, e);
+        }
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
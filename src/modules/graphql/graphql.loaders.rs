@@ -0,0 +1,171 @@
+use async_graphql::dataloader::{DataLoader, Loader};
+use sqlx::postgres::PgRow;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{current_tenant, with_tenant};
+use crate::modules::graphql::graphql_schema::{AppointmentNode, EncounterNode, PatientNode, RecordNode};
+
+// Import SQL queries from separate file
+use crate::modules::graphql::graphql_sql::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatientKey(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AppointmentKey(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EncounterKey(pub Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecordKey(pub Uuid);
+
+/// Appointments of a patient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatientAppointments(pub Uuid);
+
+/// Encounters of a patient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatientEncounters(pub Uuid);
+
+/// Medical records of a patient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatientRecords(pub Uuid);
+
+/// Medical records written during an encounter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EncounterRecords(pub Uuid);
+
+/// Loads resources for a GraphQL request. Keys requested while a level of
+/// the query resolves are collected and fetched with one `= ANY($1)` query
+/// per key type, so a list of 50 patients with their appointments costs two
+/// queries rather than 51.
+pub struct ResourceLoader {
+    pool: PgPool,
+}
+
+impl ResourceLoader {
+    /// Loader for one request. Batches run on their own tasks, so they are
+    /// given the request's tenant.
+    pub fn for_request(pool: PgPool) -> DataLoader<Self> {
+        let tenant = current_tenant();
+        DataLoader::new(Self { pool }, move |batch| match tenant.clone() {
+            Some(context) => tokio::spawn(with_tenant(context, batch)),
+            None => tokio::spawn(batch),
+        })
+    }
+
+    async fn fetch<T>(&self, sql: &str, ids: Vec<Uuid>, map: fn(&PgRow) -> Result<T, HimsError>) -> Result<Vec<T>, Arc<HimsError>> {
+        let rows = sqlx::query(sql)
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Arc::new(HimsError::DatabaseError(e.to_string())))?;
+
+        rows.iter().map(map).collect::<Result<_, _>>().map_err(Arc::new)
+    }
+}
+
+/// Items by owner, keeping the order the query returned them in
+fn group<K: Hash + Eq, T>(items: Vec<T>, owner: impl Fn(&T) -> Option<K>) -> HashMap<K, Vec<T>> {
+    let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+    for item in items {
+        if let Some(key) = owner(&item) {
+            groups.entry(key).or_default().push(item);
+        }
+    }
+    groups
+}
+
+impl Loader<PatientKey> for ResourceLoader {
+    type Value = PatientNode;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[PatientKey]) -> Result<HashMap<PatientKey, PatientNode>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(PATIENTS_BY_ID, ids, PatientNode::from_row).await?;
+        Ok(nodes.into_iter().map(|node| (PatientKey(node.id), node)).collect())
+    }
+}
+
+impl Loader<AppointmentKey> for ResourceLoader {
+    type Value = AppointmentNode;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[AppointmentKey]) -> Result<HashMap<AppointmentKey, AppointmentNode>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(APPOINTMENTS_BY_ID, ids, AppointmentNode::from_row).await?;
+        Ok(nodes.into_iter().map(|node| (AppointmentKey(node.id), node)).collect())
+    }
+}
+
+impl Loader<EncounterKey> for ResourceLoader {
+    type Value = EncounterNode;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[EncounterKey]) -> Result<HashMap<EncounterKey, EncounterNode>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(ENCOUNTERS_BY_ID, ids, EncounterNode::from_row).await?;
+        Ok(nodes.into_iter().map(|node| (EncounterKey(node.id), node)).collect())
+    }
+}
+
+impl Loader<RecordKey> for ResourceLoader {
+    type Value = RecordNode;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[RecordKey]) -> Result<HashMap<RecordKey, RecordNode>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(RECORDS_BY_ID, ids, RecordNode::from_row).await?;
+        Ok(nodes.into_iter().map(|node| (RecordKey(node.id), node)).collect())
+    }
+}
+
+impl Loader<PatientAppointments> for ResourceLoader {
+    type Value = Vec<AppointmentNode>;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[PatientAppointments]) -> Result<HashMap<PatientAppointments, Vec<AppointmentNode>>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(APPOINTMENTS_BY_PATIENT, ids, AppointmentNode::from_row).await?;
+        Ok(group(nodes, |node| node.patient_id.map(PatientAppointments)))
+    }
+}
+
+impl Loader<PatientEncounters> for ResourceLoader {
+    type Value = Vec<EncounterNode>;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[PatientEncounters]) -> Result<HashMap<PatientEncounters, Vec<EncounterNode>>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(ENCOUNTERS_BY_PATIENT, ids, EncounterNode::from_row).await?;
+        Ok(group(nodes, |node| Some(PatientEncounters(node.patient_id))))
+    }
+}
+
+impl Loader<PatientRecords> for ResourceLoader {
+    type Value = Vec<RecordNode>;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[PatientRecords]) -> Result<HashMap<PatientRecords, Vec<RecordNode>>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(RECORDS_BY_PATIENT, ids, RecordNode::from_row).await?;
+        Ok(group(nodes, |node| Some(PatientRecords(node.patient_id))))
+    }
+}
+
+impl Loader<EncounterRecords> for ResourceLoader {
+    type Value = Vec<RecordNode>;
+    type Error = Arc<HimsError>;
+
+    async fn load(&self, keys: &[EncounterRecords]) -> Result<HashMap<EncounterRecords, Vec<RecordNode>>, Self::Error> {
+        let ids = keys.iter().map(|key| key.0).collect();
+        let nodes = self.fetch(RECORDS_BY_ENCOUNTER, ids, RecordNode::from_row).await?;
+        Ok(group(nodes, |node| node.encounter_id.map(EncounterRecords)))
+    }
+}
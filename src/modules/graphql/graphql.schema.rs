@@ -0,0 +1,471 @@
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Json, Object, Result, Schema};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::appointment::{AppointmentSearch, AppointmentService};
+use crate::modules::authorization::{Action, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::graphql::graphql_loaders::{
+    AppointmentKey, EncounterKey, EncounterRecords, PatientAppointments, PatientEncounters, PatientKey, PatientRecords,
+    RecordKey, ResourceLoader,
+};
+use crate::modules::patient::{PatientSearch, PatientService};
+use crate::utils::fhir_search::parse_search_query;
+
+pub type HimsSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Schema over patients, appointments, encounters and medical records.
+/// Requests must carry a [`DataLoader<ResourceLoader>`], a
+/// [`FieldAuthorizer`] and the patient and appointment services.
+pub fn build_schema(max_depth: usize, max_complexity: usize) -> HimsSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(max_depth)
+        .limit_complexity(max_complexity)
+        .finish()
+}
+
+fn loader<'a>(ctx: &Context<'a>) -> Result<&'a DataLoader<ResourceLoader>> {
+    ctx.data::<DataLoader<ResourceLoader>>()
+}
+
+fn authorizer<'a>(ctx: &Context<'a>) -> Result<&'a FieldAuthorizer> {
+    ctx.data::<FieldAuthorizer>()
+}
+
+fn version_id(meta: &Value) -> Option<String> {
+    meta["versionId"].as_str().map(str::to_string)
+}
+
+/// Present JSONB arrays that are SQL NULL as empty
+fn list(value: &Option<Value>) -> Json<Value> {
+    Json(value.clone().unwrap_or_else(|| Value::Array(Vec::new())))
+}
+
+#[derive(Debug, Clone)]
+pub struct PatientNode {
+    pub id: Uuid,
+    active: bool,
+    identifier: Value,
+    name: Value,
+    telecom: Option<Value>,
+    gender: String,
+    birth_date: Option<NaiveDate>,
+    address: Option<Value>,
+    marital_status: Option<Value>,
+    meta: Value,
+}
+
+impl PatientNode {
+    pub fn from_row(row: &PgRow) -> std::result::Result<Self, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(Self {
+            id: row.try_get("id").map_err(read)?,
+            active: row.try_get("active").map_err(read)?,
+            identifier: row.try_get("identifier").map_err(read)?,
+            name: row.try_get("name").map_err(read)?,
+            telecom: row.try_get("telecom").map_err(read)?,
+            gender: row.try_get("gender").map_err(read)?,
+            birth_date: row.try_get("birth_date").map_err(read)?,
+            address: row.try_get("address").map_err(read)?,
+            marital_status: row.try_get("marital_status").map_err(read)?,
+            meta: row.try_get("meta").map_err(read)?,
+        })
+    }
+}
+
+/// A patient; fields use the FHIR R4 Patient shapes
+#[Object(name = "Patient")]
+impl PatientNode {
+    async fn id(&self) -> Uuid {
+        self.id
+    }
+
+    async fn version_id(&self) -> Option<String> {
+        version_id(&self.meta)
+    }
+
+    async fn active(&self) -> bool {
+        self.active
+    }
+
+    async fn identifier(&self) -> Json<Value> {
+        Json(self.identifier.clone())
+    }
+
+    async fn name(&self) -> Json<Value> {
+        Json(self.name.clone())
+    }
+
+    async fn telecom(&self) -> Json<Value> {
+        list(&self.telecom)
+    }
+
+    async fn gender(&self) -> &str {
+        &self.gender
+    }
+
+    async fn birth_date(&self) -> Option<NaiveDate> {
+        self.birth_date
+    }
+
+    async fn address(&self) -> Json<Value> {
+        list(&self.address)
+    }
+
+    async fn marital_status(&self) -> Option<Json<Value>> {
+        self.marital_status.clone().map(Json)
+    }
+
+    /// Appointments the user may read, latest first
+    async fn appointments(&self, ctx: &Context<'_>) -> Result<Vec<AppointmentNode>> {
+        let appointments = loader(ctx)?.load_one(PatientAppointments(self.id)).await?.unwrap_or_default();
+        Ok(authorizer(ctx)?
+            .retain(Action::Read, appointments, |appointment| Resource::Appointment(appointment.id))
+            .await?)
+    }
+
+    /// Encounters the user may read, latest first
+    async fn encounters(&self, ctx: &Context<'_>) -> Result<Vec<EncounterNode>> {
+        let encounters = loader(ctx)?.load_one(PatientEncounters(self.id)).await?.unwrap_or_default();
+        Ok(authorizer(ctx)?
+            .retain(Action::Read, encounters, |encounter| Resource::Encounter(encounter.id))
+            .await?)
+    }
+
+    /// Medical records the user may read, latest first
+    async fn medical_records(&self, ctx: &Context<'_>) -> Result<Vec<RecordNode>> {
+        let records = loader(ctx)?.load_one(PatientRecords(self.id)).await?.unwrap_or_default();
+        Ok(authorizer(ctx)?
+            .retain(Action::Read, records, |record| Resource::MedicalRecord(record.id))
+            .await?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AppointmentNode {
+    pub id: Uuid,
+    status: String,
+    pub patient_id: Option<Uuid>,
+    practitioner_id: Option<Uuid>,
+    service_type: Option<Value>,
+    reason_code: Option<Value>,
+    description: Option<String>,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    minutes_duration: Option<i32>,
+    participant: Value,
+    meta: Value,
+}
+
+impl AppointmentNode {
+    pub fn from_row(row: &PgRow) -> std::result::Result<Self, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(Self {
+            id: row.try_get("id").map_err(read)?,
+            status: row.try_get("status").map_err(read)?,
+            patient_id: row.try_get("patient_id").map_err(read)?,
+            practitioner_id: row.try_get("practitioner_id").map_err(read)?,
+            service_type: row.try_get("service_type").map_err(read)?,
+            reason_code: row.try_get("reason_code").map_err(read)?,
+            description: row.try_get("description").map_err(read)?,
+            start_time: row.try_get("start_time").map_err(read)?,
+            end_time: row.try_get("end_time").map_err(read)?,
+            minutes_duration: row.try_get("minutes_duration").map_err(read)?,
+            participant: row.try_get("participant").map_err(read)?,
+            meta: row.try_get("meta").map_err(read)?,
+        })
+    }
+}
+
+/// An appointment; fields use the FHIR R4 Appointment shapes
+#[Object(name = "Appointment")]
+impl AppointmentNode {
+    async fn id(&self) -> Uuid {
+        self.id
+    }
+
+    async fn version_id(&self) -> Option<String> {
+        version_id(&self.meta)
+    }
+
+    async fn status(&self) -> &str {
+        &self.status
+    }
+
+    async fn service_type(&self) -> Json<Value> {
+        list(&self.service_type)
+    }
+
+    async fn reason_code(&self) -> Json<Value> {
+        list(&self.reason_code)
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    async fn start(&self) -> Option<DateTime<Utc>> {
+        self.start_time
+    }
+
+    async fn end(&self) -> Option<DateTime<Utc>> {
+        self.end_time
+    }
+
+    async fn minutes_duration(&self) -> Option<i32> {
+        self.minutes_duration
+    }
+
+    async fn participant(&self) -> Json<Value> {
+        Json(self.participant.clone())
+    }
+
+    async fn practitioner_id(&self) -> Option<Uuid> {
+        self.practitioner_id
+    }
+
+    async fn patient(&self, ctx: &Context<'_>) -> Result<Option<PatientNode>> {
+        match self.patient_id {
+            Some(id) => patient(ctx, id).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EncounterNode {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    status: String,
+    class: Value,
+    encounter_type: Option<Value>,
+    period: Option<Value>,
+    reason_code: Option<Value>,
+    diagnosis: Option<Value>,
+    meta: Value,
+}
+
+impl EncounterNode {
+    pub fn from_row(row: &PgRow) -> std::result::Result<Self, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(Self {
+            id: row.try_get("id").map_err(read)?,
+            patient_id: row.try_get("subject").map_err(read)?,
+            status: row.try_get("status").map_err(read)?,
+            class: row.try_get("class").map_err(read)?,
+            encounter_type: row.try_get("type").map_err(read)?,
+            period: row.try_get("period").map_err(read)?,
+            reason_code: row.try_get("reason_code").map_err(read)?,
+            diagnosis: row.try_get("diagnosis").map_err(read)?,
+            meta: row.try_get("meta").map_err(read)?,
+        })
+    }
+}
+
+/// A visit; fields use the FHIR R4 Encounter shapes
+#[Object(name = "Encounter")]
+impl EncounterNode {
+    async fn id(&self) -> Uuid {
+        self.id
+    }
+
+    async fn version_id(&self) -> Option<String> {
+        version_id(&self.meta)
+    }
+
+    async fn status(&self) -> &str {
+        &self.status
+    }
+
+    async fn class(&self) -> Json<Value> {
+        Json(self.class.clone())
+    }
+
+    #[graphql(name = "type")]
+    async fn encounter_type(&self) -> Json<Value> {
+        list(&self.encounter_type)
+    }
+
+    async fn period(&self) -> Option<Json<Value>> {
+        self.period.clone().map(Json)
+    }
+
+    async fn reason_code(&self) -> Json<Value> {
+        list(&self.reason_code)
+    }
+
+    /// Diagnoses are clinical results, so they need more than read access
+    /// to the encounter
+    async fn diagnosis(&self, ctx: &Context<'_>) -> Result<Json<Value>> {
+        authorizer(ctx)?.require(Action::ViewResults, Resource::Encounter(self.id)).await?;
+        Ok(list(&self.diagnosis))
+    }
+
+    async fn patient(&self, ctx: &Context<'_>) -> Result<Option<PatientNode>> {
+        patient(ctx, self.patient_id).await
+    }
+
+    /// Medical records of the encounter the user may read
+    async fn medical_records(&self, ctx: &Context<'_>) -> Result<Vec<RecordNode>> {
+        let records = loader(ctx)?.load_one(EncounterRecords(self.id)).await?.unwrap_or_default();
+        Ok(authorizer(ctx)?
+            .retain(Action::Read, records, |record| Resource::MedicalRecord(record.id))
+            .await?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordNode {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    record_type: String,
+    status: String,
+    author: Value,
+    content: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    meta: Value,
+}
+
+impl RecordNode {
+    pub fn from_row(row: &PgRow) -> std::result::Result<Self, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(Self {
+            id: row.try_get("id").map_err(read)?,
+            patient_id: row.try_get("patient_id").map_err(read)?,
+            encounter_id: row.try_get("encounter_id").map_err(read)?,
+            record_type: row.try_get("record_type").map_err(read)?,
+            status: row.try_get("status").map_err(read)?,
+            author: row.try_get("author").map_err(read)?,
+            content: row.try_get("content").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+            updated_at: row.try_get("updated_at").map_err(read)?,
+            meta: row.try_get("meta").map_err(read)?,
+        })
+    }
+}
+
+/// A clinical document, e.g. a progress note or discharge summary
+#[Object(name = "MedicalRecord")]
+impl RecordNode {
+    async fn id(&self) -> Uuid {
+        self.id
+    }
+
+    async fn version_id(&self) -> Option<String> {
+        version_id(&self.meta)
+    }
+
+    async fn record_type(&self) -> &str {
+        &self.record_type
+    }
+
+    async fn status(&self) -> &str {
+        &self.status
+    }
+
+    async fn author(&self) -> Json<Value> {
+        Json(self.author.clone())
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+
+    /// The document text. Listing a patient's documents needs read access;
+    /// the text itself needs access to clinical results.
+    async fn content(&self, ctx: &Context<'_>) -> Result<&str> {
+        authorizer(ctx)?.require(Action::ViewResults, Resource::MedicalRecord(self.id)).await?;
+        Ok(&self.content)
+    }
+
+    async fn patient(&self, ctx: &Context<'_>) -> Result<Option<PatientNode>> {
+        patient(ctx, self.patient_id).await
+    }
+
+    async fn encounter(&self, ctx: &Context<'_>) -> Result<Option<EncounterNode>> {
+        match self.encounter_id {
+            Some(id) => {
+                authorizer(ctx)?.require(Action::Read, Resource::Encounter(id)).await?;
+                Ok(loader(ctx)?.load_one(EncounterKey(id)).await?)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+async fn patient(ctx: &Context<'_>, id: Uuid) -> Result<Option<PatientNode>> {
+    authorizer(ctx)?.require(Action::Read, Resource::Patient(id)).await?;
+    Ok(loader(ctx)?.load_one(PatientKey(id)).await?)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn patient(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<PatientNode>> {
+        patient(ctx, id).await
+    }
+
+    /// Patients matching a FHIR search, e.g. `family=Shah&birthdate=1980-04-02&_count=20`,
+    /// leaving out those the user may not read
+    async fn patients(&self, ctx: &Context<'_>, #[graphql(default)] search: String) -> Result<Vec<PatientNode>> {
+        let search = PatientSearch::from_params(&parse_search_query(&search)?)?;
+        let result = ctx.data::<Arc<PatientService>>()?.search_patients(&search).await?;
+
+        let keys: Vec<PatientKey> = result.patients.iter().map(|patient| PatientKey(patient.id)).collect();
+        let loaded = loader(ctx)?.load_many(keys.iter().copied()).await?;
+        let patients = keys.iter().filter_map(|key| loaded.get(key).cloned()).collect();
+        Ok(authorizer(ctx)?
+            .retain(Action::Read, patients, |patient| Resource::Patient(patient.id))
+            .await?)
+    }
+
+    async fn appointment(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<AppointmentNode>> {
+        authorizer(ctx)?.require(Action::Read, Resource::Appointment(id)).await?;
+        Ok(loader(ctx)?.load_one(AppointmentKey(id)).await?)
+    }
+
+    /// Appointments matching a FHIR search, e.g. `status=booked&date=ge2024-01-01`,
+    /// leaving out those the user may not read
+    async fn appointments(&self, ctx: &Context<'_>, #[graphql(default)] search: String) -> Result<Vec<AppointmentNode>> {
+        let search = AppointmentSearch::from_params(&parse_search_query(&search)?)?;
+        let result = ctx.data::<Arc<AppointmentService>>()?.search(&search).await?;
+
+        let keys: Vec<AppointmentKey> = result
+            .appointments
+            .iter()
+            .map(|appointment| AppointmentKey(appointment.id))
+            .collect();
+        let loaded = loader(ctx)?.load_many(keys.iter().copied()).await?;
+        let appointments = keys.iter().filter_map(|key| loaded.get(key).cloned()).collect();
+        Ok(authorizer(ctx)?
+            .retain(Action::Read, appointments, |appointment| Resource::Appointment(appointment.id))
+            .await?)
+    }
+
+    async fn encounter(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<EncounterNode>> {
+        authorizer(ctx)?.require(Action::Read, Resource::Encounter(id)).await?;
+        Ok(loader(ctx)?.load_one(EncounterKey(id)).await?)
+    }
+
+    async fn medical_record(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<RecordNode>> {
+        authorizer(ctx)?.require(Action::Read, Resource::MedicalRecord(id)).await?;
+        Ok(loader(ctx)?.load_one(RecordKey(id)).await?)
+    }
+}
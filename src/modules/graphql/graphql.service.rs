@@ -0,0 +1,275 @@
+use async_graphql::Variables;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::appointment::AppointmentService;
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::graphql::graphql_loaders::ResourceLoader;
+use crate::modules::graphql::graphql_schema::{build_schema, HimsSchema};
+use crate::modules::patient::PatientService;
+use crate::modules::auth::AuthContext;
+use crate::utils::auth::extract_role_from_headers;
+
+// Import SQL queries from separate file
+use crate::modules::graphql::graphql_sql::*;
+
+/// GraphQL layer configuration
+#[derive(Debug, Clone)]
+pub struct GraphqlConfig {
+    /// Token roles that may only run persisted queries, e.g. patient portal users
+    pub persisted_only_roles: Vec<String>,
+    pub max_depth: usize,
+    pub max_complexity: usize,
+}
+
+impl Default for GraphqlConfig {
+    fn default() -> Self {
+        Self {
+            persisted_only_roles: vec!["patient".to_string()],
+            max_depth: 8,
+            max_complexity: 500,
+        }
+    }
+}
+
+/// A GraphQL request as sent over HTTP. A persisted query is named by the
+/// SHA-256 of its text in `extensions.persistedQuery.sha256Hash`, with or
+/// without the text itself.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphqlRequest {
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub operation_name: Option<String>,
+    #[serde(default)]
+    pub variables: Option<Value>,
+    #[serde(default)]
+    pub extensions: Option<Value>,
+}
+
+impl GraphqlRequest {
+    pub fn persisted_hash(&self) -> Option<&str> {
+        self.extensions.as_ref()?["persistedQuery"]["sha256Hash"].as_str()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PersistedQueryRequest {
+    pub name: String,
+    pub query: String,
+}
+
+/// A query on the allowlist
+#[derive(Debug, Clone, Serialize)]
+pub struct PersistedQuery {
+    pub hash: String,
+    pub name: String,
+    pub query: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Hex SHA-256 of a query's text, as clients compute it
+pub fn query_hash(query: &str) -> String {
+    Sha256::digest(query.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Runs GraphQL requests with per-request loaders and authorization, and
+/// keeps the persisted query allowlist
+pub struct GraphqlService {
+    pool: PgPool,
+    schema: HimsSchema,
+    config: GraphqlConfig,
+    patients: Arc<PatientService>,
+    appointments: Arc<AppointmentService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+}
+
+impl GraphqlService {
+    pub fn new(
+        pool: PgPool,
+        config: GraphqlConfig,
+        patients: Arc<PatientService>,
+        appointments: Arc<AppointmentService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        Self {
+            schema: build_schema(config.max_depth, config.max_complexity),
+            pool,
+            config,
+            patients,
+            appointments,
+            authorization_engine,
+        }
+    }
+
+    /// The schema in SDL
+    pub fn sdl(&self) -> String {
+        self.schema.sdl()
+    }
+
+    /// Whether the caller's token role limits it to persisted queries
+    pub fn is_persisted_only(&self, headers: &HeaderMap) -> bool {
+        extract_role_from_headers(headers).is_some_and(|role| self.config.persisted_only_roles.contains(&role))
+    }
+
+    /// Execute a request as the user of `auth`. Errors are for requests that cannot
+    /// run at all; errors while resolving fields are in the response.
+    pub async fn execute(&self, auth: &AuthContext, headers: &HeaderMap, request: GraphqlRequest) -> Result<async_graphql::Response, HimsError> {
+        let query = self.resolve_query(&request, self.is_persisted_only(headers)).await?;
+
+        let authorizer = FieldAuthorizer::for_headers(self.authorization_engine.clone(), auth, headers).await?;
+        let mut graphql_request = async_graphql::Request::new(query)
+            .data(ResourceLoader::for_request(self.pool.clone()))
+            .data(authorizer)
+            .data(self.patients.clone())
+            .data(self.appointments.clone());
+        if let Some(operation_name) = request.operation_name {
+            graphql_request = graphql_request.operation_name(operation_name);
+        }
+        if let Some(variables) = request.variables {
+            graphql_request = graphql_request.variables(Variables::from_json(variables));
+        }
+
+        Ok(self.schema.execute(graphql_request).await)
+    }
+
+    /// Text of the query to run. Callers restricted to persisted queries
+    /// always run the allowlisted text for the hash they name.
+    async fn resolve_query(&self, request: &GraphqlRequest, persisted_only: bool) -> Result<String, HimsError> {
+        match (request.persisted_hash(), &request.query) {
+            (Some(hash), Some(query)) if !persisted_only => {
+                if query_hash(query) != hash.to_ascii_lowercase() {
+                    return Err(HimsError::ValidationError {
+                        message: "provided sha256Hash does not match query".to_string(),
+                    });
+                }
+                Ok(query.clone())
+            }
+            (Some(hash), _) => match self.get_persisted_query(hash).await? {
+                Some(persisted) => Ok(persisted.query),
+                None if persisted_only => Err(HimsError::SecurityError {
+                    message: "Query is not on the persisted query allowlist".to_string(),
+                }),
+                None => Err(HimsError::ValidationError {
+                    message: "PersistedQueryNotFound".to_string(),
+                }),
+            },
+            (None, Some(_)) if persisted_only => Err(HimsError::SecurityError {
+                message: "Only persisted queries may be run by this client".to_string(),
+            }),
+            (None, Some(query)) => Ok(query.clone()),
+            (None, None) => Err(HimsError::ValidationError {
+                message: "Request has no query".to_string(),
+            }),
+        }
+    }
+
+    /// Add a query to the allowlist. It must parse; whether it is valid
+    /// against the schema is checked when it runs.
+    pub async fn register_persisted_query(&self, actor: Uuid, request: PersistedQueryRequest) -> Result<PersistedQuery, HimsError> {
+        async_graphql::parser::parse_query(&request.query).map_err(|e| HimsError::ValidationError {
+            message: format!("Invalid GraphQL query: {}", e),
+        })?;
+
+        let row = sqlx::query(UPSERT_PERSISTED_QUERY)
+            .bind(query_hash(&request.query))
+            .bind(&request.name)
+            .bind(&request.query)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Self::row_to_persisted_query(&row)
+    }
+
+    pub async fn get_persisted_query(&self, hash: &str) -> Result<Option<PersistedQuery>, HimsError> {
+        let row = sqlx::query(GET_PERSISTED_QUERY)
+            .bind(hash.to_ascii_lowercase())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        row.as_ref().map(Self::row_to_persisted_query).transpose()
+    }
+
+    pub async fn list_persisted_queries(&self) -> Result<Vec<PersistedQuery>, HimsError> {
+        let rows = sqlx::query(LIST_PERSISTED_QUERIES)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        rows.iter().map(Self::row_to_persisted_query).collect()
+    }
+
+    pub async fn delete_persisted_query(&self, hash: &str) -> Result<bool, HimsError> {
+        let result = sqlx::query(DELETE_PERSISTED_QUERY)
+            .bind(hash.to_ascii_lowercase())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn row_to_persisted_query(row: &PgRow) -> Result<PersistedQuery, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(PersistedQuery {
+            hash: row.try_get("hash").map_err(read)?,
+            name: row.try_get("name").map_err(read)?,
+            query: row.try_get("query").map_err(read)?,
+            created_by: row.try_get("created_by").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_query_hash() {
+        assert_eq!(
+            query_hash("{ patient(id: \"1\") { id } }"),
+            "8f12c36e6e58f1ee7dbb085cfb15f16705175fc652d8bd75353c3f9177aca298"
+        );
+    }
+
+    #[test]
+    fn test_persisted_hash() {
+        let request: GraphqlRequest = serde_json::from_value(json!({
+            "operationName": "PortalHome",
+            "extensions": { "persistedQuery": { "version": 1, "sha256Hash": "abc123" } }
+        }))
+        .unwrap();
+        assert_eq!(request.persisted_hash(), Some("abc123"));
+        assert_eq!(request.operation_name.as_deref(), Some("PortalHome"));
+        assert!(request.query.is_none());
+
+        let request: GraphqlRequest = serde_json::from_value(json!({ "query": "{ __typename }" })).unwrap();
+        assert_eq!(request.persisted_hash(), None);
+    }
+
+    #[test]
+    fn test_schema_exposes_core_resources() {
+        let sdl = build_schema(8, 500).sdl();
+        for type_name in ["type Patient", "type Appointment", "type Encounter", "type MedicalRecord", "type QueryRoot"] {
+            assert!(sdl.contains(type_name), "missing {}", type_name);
+        }
+        assert!(sdl.contains("medicalRecords: [MedicalRecord!]!"));
+    }
+}
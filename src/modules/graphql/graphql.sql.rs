@@ -0,0 +1,84 @@
+//! GraphQL SQL Queries
+//!
+//! This file contains all SQL queries used by the GraphQL layer
+//! for clean separation of concerns and better maintainability.
+//! Resource queries take an array of keys so a whole level of a
+//! query is fetched at once.
+
+pub const PATIENTS_BY_ID: &str = r#"
+    SELECT id, active, identifier, name, telecom, gender, birth_date, address, marital_status, meta
+    FROM patients
+    WHERE id = ANY($1) AND active = true
+"#;
+
+pub const APPOINTMENTS_BY_ID: &str = r#"
+    SELECT id, status, patient_id, practitioner_id, service_type, reason_code, description,
+           start_time, end_time, minutes_duration, participant, meta
+    FROM appointments
+    WHERE id = ANY($1)
+"#;
+
+pub const APPOINTMENTS_BY_PATIENT: &str = r#"
+    SELECT id, status, patient_id, practitioner_id, service_type, reason_code, description,
+           start_time, end_time, minutes_duration, participant, meta
+    FROM appointments
+    WHERE patient_id = ANY($1)
+    ORDER BY start_time DESC NULLS LAST
+"#;
+
+pub const ENCOUNTERS_BY_ID: &str = r#"
+    SELECT id, subject, status, class, type, period, reason_code, diagnosis, meta
+    FROM encounters
+    WHERE id = ANY($1)
+"#;
+
+pub const ENCOUNTERS_BY_PATIENT: &str = r#"
+    SELECT id, subject, status, class, type, period, reason_code, diagnosis, meta
+    FROM encounters
+    WHERE subject = ANY($1)
+    ORDER BY period->>'start' DESC NULLS LAST
+"#;
+
+pub const RECORDS_BY_ID: &str = r#"
+    SELECT id, patient_id, encounter_id, record_type, status, author, content, created_at, updated_at, meta
+    FROM medical_records
+    WHERE id = ANY($1) AND deleted_at IS NULL
+"#;
+
+pub const RECORDS_BY_PATIENT: &str = r#"
+    SELECT id, patient_id, encounter_id, record_type, status, author, content, created_at, updated_at, meta
+    FROM medical_records
+    WHERE patient_id = ANY($1) AND deleted_at IS NULL
+    ORDER BY created_at DESC
+"#;
+
+pub const RECORDS_BY_ENCOUNTER: &str = r#"
+    SELECT id, patient_id, encounter_id, record_type, status, author, content, created_at, updated_at, meta
+    FROM medical_records
+    WHERE encounter_id = ANY($1) AND deleted_at IS NULL
+    ORDER BY created_at DESC
+"#;
+
+/// Registering the same query again only renames it
+pub const UPSERT_PERSISTED_QUERY: &str = r#"
+    INSERT INTO graphql_persisted_queries (hash, name, query, created_by)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT (hash) DO UPDATE SET name = EXCLUDED.name
+    RETURNING hash, name, query, created_by, created_at
+"#;
+
+pub const GET_PERSISTED_QUERY: &str = r#"
+    SELECT hash, name, query, created_by, created_at
+    FROM graphql_persisted_queries
+    WHERE hash = $1
+"#;
+
+pub const LIST_PERSISTED_QUERIES: &str = r#"
+    SELECT hash, name, query, created_by, created_at
+    FROM graphql_persisted_queries
+    ORDER BY name
+"#;
+
+pub const DELETE_PERSISTED_QUERY: &str = r#"
+    DELETE FROM graphql_persisted_queries WHERE hash = $1
+"#;
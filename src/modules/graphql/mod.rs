@@ -0,0 +1,69 @@
+//! GraphQL Module
+//!
+//! This module provides a GraphQL API over the core resources including:
+//! - Patients, appointments, encounters and medical records with their links
+//! - Field-level authorization through the authorization engine
+//! - Data loaders batching each level of a query into one SQL query
+//! - A persisted query allowlist, the only queries patient portal users may run
+
+#[path = "graphql.authz.rs"]
+pub mod graphql_authz;
+#[path = "graphql.controller.rs"]
+pub mod graphql_controller;
+#[path = "graphql.loaders.rs"]
+pub mod graphql_loaders;
+#[path = "graphql.schema.rs"]
+pub mod graphql_schema;
+#[path = "graphql.service.rs"]
+pub mod graphql_service;
+#[path = "graphql.sql.rs"]
+pub mod graphql_sql;
+
+pub use graphql_controller::GraphqlController;
+pub use graphql_schema::{build_schema, HimsSchema};
+pub use graphql_service::{GraphqlConfig, GraphqlRequest, GraphqlService, PersistedQuery};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::appointment::AppointmentService;
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::patient::PatientService;
+use crate::utils::api_router::ApiRouter;
+
+/// GraphQL Module Configuration
+pub struct GraphqlModule {
+    pub service: Arc<GraphqlService>,
+    pub controller: Arc<GraphqlController>,
+}
+
+impl GraphqlModule {
+    /// Create a new GraphQL Module with dependency injection
+    pub fn new(
+        db_pool: PgPool,
+        patient_service: Arc<PatientService>,
+        appointment_service: Arc<AppointmentService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        let service = Arc::new(GraphqlService::new(
+            db_pool,
+            GraphqlConfig::default(),
+            patient_service,
+            appointment_service,
+            authorization_engine,
+        ));
+        let controller = Arc::new(GraphqlController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register GraphQL routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<GraphqlService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,105 @@
+use axum::http::HeaderMap;
+use tonic::{Request, Status};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_jwt::token_verifier;
+use crate::modules::auth::AuthContext;
+
+/// Caller of a gRPC request, with its metadata as HTTP headers so the
+/// helpers shared with the REST layer can read it. Only a verified bearer
+/// token in `authorization` metadata names the caller. Its source IP is the
+/// one `AuthContext::attach` resolved, never one the metadata claims.
+pub fn caller<T>(request: &Request<T>) -> Result<(AuthContext, HeaderMap), Status> {
+    let headers = request.metadata().clone().into_headers();
+    let token = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+    let verifier = token_verifier().ok_or_else(|| Status::unauthenticated("Token verification is not configured"))?;
+    let claims = verifier.validate_access_token(token).map_err(|e| {
+        tracing::warn!("Rejected gRPC bearer token: {}", e);
+        Status::unauthenticated("Invalid or missing authentication")
+    })?;
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| Status::unauthenticated("Invalid subject in token"))?;
+    let auth = request
+        .extensions()
+        .get::<AuthContext>()
+        .filter(|auth| auth.user_id == user_id)
+        .cloned()
+        .or_else(|| AuthContext::from_headers(&headers))
+        .unwrap_or_else(|| AuthContext::new(user_id));
+    Ok((auth, headers))
+}
+
+/// gRPC status for a service error, as `error_response` maps them to HTTP
+pub fn to_status(context: &str, e: HimsError) -> Status {
+    match &e {
+        HimsError::SecurityError { .. } => {
+            tracing::warn!("{}: {}", context, e);
+            Status::permission_denied(format!("{}: {}", context, e))
+        }
+        HimsError::ValidationError { .. } => {
+            tracing::error!("{}: {}", context, e);
+            Status::invalid_argument(format!("{}: {}", context, e))
+        }
+        _ => {
+            tracing::error!("{}: {}", context, e);
+            Status::internal(format!("{}: {}", context, e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::auth::auth_jwt::test_tokens;
+    use crate::modules::auth::AuthenticatedUser;
+    use tonic::Code;
+
+    #[test]
+    fn test_caller_reads_metadata() {
+        let user_id = Uuid::new_v4();
+        let tokens = test_tokens(&AuthenticatedUser {
+            id: user_id.to_string(),
+            username: "clinician".to_string(),
+            role: "physician".to_string(),
+            permissions: vec![],
+        });
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {}", tokens.access_token).parse().unwrap());
+        request.metadata_mut().insert("x-purpose-of-use", "TREAT".parse().unwrap());
+
+        let (auth, headers) = caller(&request).unwrap();
+        assert_eq!(auth.user_id, user_id);
+        assert_eq!(auth.source_ip, None);
+        assert!(headers.contains_key("x-purpose-of-use"));
+
+        let status = caller(&Request::new(())).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+
+        // A user named in metadata is not a caller
+        let mut request = Request::new(());
+        request.metadata_mut().insert("x-user-id", user_id.to_string().parse().unwrap());
+        assert_eq!(caller(&request).unwrap_err().code(), Code::Unauthenticated);
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert("authorization", "Bearer forged".parse().unwrap());
+        assert_eq!(caller(&request).unwrap_err().code(), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_to_status() {
+        let status = to_status("Check failed", HimsError::SecurityError { message: "no".to_string() });
+        assert_eq!(status.code(), Code::PermissionDenied);
+
+        let status = to_status("Lookup failed", HimsError::ValidationError { message: "bad".to_string() });
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let status = to_status("Lookup failed", HimsError::DatabaseError("down".to_string()));
+        assert_eq!(status.code(), Code::Internal);
+    }
+}
@@ -0,0 +1,124 @@
+use axum::http::HeaderMap;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{
+    Action, AuthorizationEngine, AuthorizationRequest, AuthorizationResponse, HimsAuthorizationEngine, Resource,
+};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::grpc::grpc_auth::{caller, to_status};
+use crate::modules::grpc::proto::{self, authorization_server::Authorization};
+use crate::modules::role::RoleService;
+
+/// Authorization engine decisions over gRPC, for systems enforcing HIMS
+/// policy on their own data
+pub struct AuthorizationGrpc {
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    role_service: RoleService,
+}
+
+impl AuthorizationGrpc {
+    pub fn new(authorization_engine: Arc<HimsAuthorizationEngine>, role_service: RoleService) -> Self {
+        Self {
+            authorization_engine,
+            role_service,
+        }
+    }
+
+    /// Engine request for a check. Decisions for users other than the
+    /// caller reveal their access, so they need the audit permission.
+    async fn engine_request(
+        &self,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        check: &proto::CheckRequest,
+    ) -> Result<AuthorizationRequest, HimsError> {
+        let caller_id = auth.user_id;
+        let subject = if check.subject_user_id.is_empty() {
+            caller_id
+        } else {
+            Uuid::parse_str(&check.subject_user_id).map_err(|_| HimsError::ValidationError {
+                message: format!("Invalid subject user ID: {}", check.subject_user_id),
+            })?
+        };
+        if subject != caller_id && !self.role_service.get_user_permissions(caller_id).await?.contains(&Action::Audit) {
+            tracing::warn!("User {} lacks {} permission to check access of {}", caller_id, Action::Audit, subject);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", Action::Audit),
+            });
+        }
+
+        if check.action.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Action is required".to_string(),
+            });
+        }
+        let action: Action = check.action.parse().map_err(|message| HimsError::ValidationError { message })?;
+        let resource: Resource = check.resource.parse().map_err(|message| HimsError::ValidationError { message })?;
+
+        // The subject, in the caller's session and from the caller's address
+        let subject = AuthContext {
+            user_id: subject,
+            ..auth.clone()
+        };
+        let authorizer = FieldAuthorizer::for_headers(self.authorization_engine.clone(), &subject, headers).await?;
+        Ok(authorizer.request(action, resource))
+    }
+
+    fn to_message(response: AuthorizationResponse) -> proto::CheckResponse {
+        proto::CheckResponse {
+            allowed: FieldAuthorizer::permits(&response),
+            decision: response.decision.to_string(),
+            reasons: response.reasons,
+            requirements: response.requirements,
+            restrictions: response.restrictions,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Authorization for AuthorizationGrpc {
+    async fn check(&self, request: Request<proto::CheckRequest>) -> Result<Response<proto::CheckResponse>, Status> {
+        let (auth, headers) = caller(&request)?;
+        let engine_request = self
+            .engine_request(&auth, &headers, request.get_ref())
+            .await
+            .map_err(|e| to_status("Failed to check access", e))?;
+
+        let response = self.authorization_engine.check(engine_request).await.map_err(|e| {
+            to_status(
+                "Failed to check access",
+                HimsError::InternalError { message: e.to_string() },
+            )
+        })?;
+        Ok(Response::new(Self::to_message(response)))
+    }
+
+    async fn check_batch(
+        &self,
+        request: Request<proto::CheckBatchRequest>,
+    ) -> Result<Response<proto::CheckBatchResponse>, Status> {
+        let (auth, headers) = caller(&request)?;
+        let mut engine_requests = Vec::with_capacity(request.get_ref().checks.len());
+        for check in &request.get_ref().checks {
+            engine_requests.push(
+                self.engine_request(&auth, &headers, check)
+                    .await
+                    .map_err(|e| to_status("Failed to check access", e))?,
+            );
+        }
+
+        let responses = self.authorization_engine.check_batch(engine_requests).await.map_err(|e| {
+            to_status(
+                "Failed to check access",
+                HimsError::InternalError { message: e.to_string() },
+            )
+        })?;
+        Ok(Response::new(proto::CheckBatchResponse {
+            results: responses.into_iter().map(Self::to_message).collect(),
+        }))
+    }
+}
@@ -0,0 +1,131 @@
+use axum::http::HeaderMap;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::Patient;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::grpc::grpc_auth::{caller, to_status};
+use crate::modules::grpc::proto::{self, patient_directory_server::PatientDirectory};
+use crate::modules::patient::patient_service::PatientSearch;
+use crate::modules::patient::{PatientController, PatientService};
+
+/// Patient lookup over gRPC, on the same service as the REST API. Patients
+/// the caller may not read are treated as not existing.
+pub struct PatientDirectoryGrpc {
+    patient_service: Arc<PatientService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+}
+
+impl PatientDirectoryGrpc {
+    pub fn new(patient_service: Arc<PatientService>, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        Self {
+            patient_service,
+            authorization_engine,
+        }
+    }
+
+    async fn authorizer(&self, auth: &AuthContext, headers: &HeaderMap) -> Result<FieldAuthorizer, Status> {
+        FieldAuthorizer::for_headers(self.authorization_engine.clone(), auth, headers).await.map_err(|e| {
+            tracing::error!("Failed to establish session context: {}", e);
+            Status::internal("Failed to establish session context")
+        })
+    }
+
+    fn to_message(patient: Patient) -> proto::Patient {
+        let gender = serde_json::to_value(&patient.gender)
+            .ok()
+            .and_then(|gender| gender.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let identifiers = patient
+            .identifier
+            .iter()
+            .map(|identifier| proto::Identifier {
+                system: identifier.system.clone().unwrap_or_default(),
+                value: identifier.value.clone(),
+            })
+            .collect();
+        let names = patient
+            .name
+            .iter()
+            .map(|name| proto::HumanName {
+                text: name.text.clone().unwrap_or_default(),
+                family: name.family.clone().unwrap_or_default(),
+                given: name.given.clone(),
+            })
+            .collect();
+
+        proto::Patient {
+            id: patient.id.to_string(),
+            version_id: patient.meta.version_id.clone().unwrap_or_default(),
+            active: patient.active,
+            identifiers,
+            names,
+            gender,
+            birth_date: patient.birth_date.map(|date| date.to_string()).unwrap_or_default(),
+            // Same JSON the REST API returns, so clients need one parser for both
+            resource_json: serde_json::to_string(&PatientController::patient_to_response(patient)).unwrap_or_default(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl PatientDirectory for PatientDirectoryGrpc {
+    async fn get_patient(&self, request: Request<proto::GetPatientRequest>) -> Result<Response<proto::Patient>, Status> {
+        let (auth, headers) = caller(&request)?;
+        let id = Uuid::parse_str(&request.get_ref().id)
+            .map_err(|_| Status::invalid_argument(format!("Invalid patient ID: {}", request.get_ref().id)))?;
+
+        let authorizer = self.authorizer(&auth, &headers).await?;
+        let allowed = authorizer
+            .allowed(Action::Read, Resource::Patient(id))
+            .await
+            .map_err(|e| to_status("Failed to authorize patient lookup", e))?;
+        if !allowed {
+            tracing::warn!("User {} may not read patient {}", auth.user_id, id);
+            return Err(Status::not_found(format!("Patient with id {} not found", id)));
+        }
+
+        match self.patient_service.read_patient(id, &auth).await {
+            Ok(Some(patient)) => Ok(Response::new(Self::to_message(patient))),
+            Ok(None) => Err(Status::not_found(format!("Patient with id {} not found", id))),
+            Err(e) => Err(to_status(
+                "Failed to retrieve patient",
+                HimsError::DatabaseError(e.to_string()),
+            )),
+        }
+    }
+
+    async fn search_patients(
+        &self,
+        request: Request<proto::SearchPatientsRequest>,
+    ) -> Result<Response<proto::SearchPatientsResponse>, Status> {
+        let (auth, headers) = caller(&request)?;
+        let params: Vec<(String, String)> = request
+            .into_inner()
+            .parameters
+            .into_iter()
+            .map(|param| (param.name, param.value))
+            .collect();
+        let search = PatientSearch::from_params(&params).map_err(|e| to_status("Invalid patient search", e))?;
+
+        let result = self.patient_service.search_patients(&search).await.map_err(|e| {
+            to_status("Failed to search patients", HimsError::DatabaseError(e.to_string()))
+        })?;
+        let authorizer = self.authorizer(&auth, &headers).await?;
+        let patients = authorizer
+            .retain(Action::Read, result.patients, |patient| Resource::Patient(patient.id))
+            .await
+            .map_err(|e| to_status("Failed to authorize patient search", e))?;
+
+        Ok(Response::new(proto::SearchPatientsResponse {
+            patients: patients.into_iter().map(Self::to_message).collect(),
+            total: result.total,
+            next_cursor: result.next.map(|cursor| cursor.encode()),
+            previous_cursor: result.previous.map(|cursor| cursor.encode()),
+        }))
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+use crate::modules::grpc::grpc_auth::caller;
+use crate::modules::grpc::proto::{self, terminology_server::Terminology};
+use crate::standards::fhir::{ProfilePack, ProfileRegistry, ValueSetCode};
+
+/// Code lookup and ValueSet membership over gRPC, from the terminology of
+/// the embedded FHIR profile packs
+pub struct TerminologyGrpc {
+    registry: Arc<ProfileRegistry>,
+}
+
+impl TerminologyGrpc {
+    pub fn new(registry: Arc<ProfileRegistry>) -> Self {
+        Self { registry }
+    }
+
+    /// Terminology of every profile pack. A pack that fails to load is
+    /// logged and left out rather than failing startup.
+    pub fn with_profile_packs() -> Self {
+        let mut registry = ProfileRegistry::new();
+        for pack in ProfilePack::ALL {
+            if let Err(e) = pack.load_into(&mut registry) {
+                tracing::error!("Failed to load terminology from {}: {}", pack.package_id(), e);
+            }
+        }
+        Self::new(Arc::new(registry))
+    }
+}
+
+#[tonic::async_trait]
+impl Terminology for TerminologyGrpc {
+    async fn lookup_code(&self, request: Request<proto::LookupCodeRequest>) -> Result<Response<proto::LookupCodeResponse>, Status> {
+        caller(&request)?;
+        let lookup = request.get_ref();
+        if lookup.system.is_empty() || lookup.code.is_empty() {
+            return Err(Status::invalid_argument("System and code are required"));
+        }
+
+        let response = match self.registry.lookup_code(&lookup.system, &lookup.code) {
+            Some(concept) => proto::LookupCodeResponse {
+                found: true,
+                display: concept.display.unwrap_or_default(),
+            },
+            None => proto::LookupCodeResponse {
+                found: false,
+                display: String::new(),
+            },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn validate_code(
+        &self,
+        request: Request<proto::ValidateCodeRequest>,
+    ) -> Result<Response<proto::ValidateCodeResponse>, Status> {
+        caller(&request)?;
+        let validate = request.get_ref();
+        if validate.value_set.is_empty() || validate.code.is_empty() {
+            return Err(Status::invalid_argument("ValueSet and code are required"));
+        }
+
+        let code = ValueSetCode {
+            system: (!validate.system.is_empty()).then(|| validate.system.clone()),
+            code: validate.code.clone(),
+        };
+        let response = match self.registry.value_set_codes(&validate.value_set) {
+            Some(codes) => proto::ValidateCodeResponse {
+                valid: codes.contains(&code),
+                known_value_set: true,
+            },
+            None => proto::ValidateCodeResponse {
+                valid: false,
+                known_value_set: false,
+            },
+        };
+        Ok(Response::new(response))
+    }
+}
@@ -0,0 +1,88 @@
+//! gRPC Module
+//!
+//! This module serves the core services over gRPC for backend systems including:
+//! - Patient lookup and search on the patient service the REST API uses
+//! - Authorization engine checks, singly or in batches
+//! - Terminology lookup and ValueSet validation from the loaded profile packs
+//!
+//! Services are defined in `proto/hims/v1/hims.proto` and mounted on the REST
+//! router, so calls pass the same tenant resolution and metrics layers.
+
+#[path = "grpc.auth.rs"]
+pub mod grpc_auth;
+#[path = "grpc.authorization.rs"]
+pub mod grpc_authorization;
+#[path = "grpc.patient.rs"]
+pub mod grpc_patient;
+#[path = "grpc.terminology.rs"]
+pub mod grpc_terminology;
+
+/// Messages, servers and clients generated from `hims.proto`
+pub mod proto {
+    tonic::include_proto!("hims.v1");
+}
+
+pub use grpc_authorization::AuthorizationGrpc;
+pub use grpc_patient::PatientDirectoryGrpc;
+pub use grpc_terminology::TerminologyGrpc;
+
+use axum::{extract::Request, http, Router};
+use sqlx::PgPool;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tonic::body::BoxBody;
+use tonic::server::NamedService;
+use tower::{Service, ServiceExt};
+
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::patient::PatientService;
+use crate::modules::role::RoleService;
+use proto::authorization_server::AuthorizationServer;
+use proto::patient_directory_server::PatientDirectoryServer;
+use proto::terminology_server::TerminologyServer;
+
+/// gRPC Module Configuration
+pub struct GrpcModule {
+    pub patient_directory: Arc<PatientDirectoryGrpc>,
+    pub authorization: Arc<AuthorizationGrpc>,
+    pub terminology: Arc<TerminologyGrpc>,
+}
+
+impl GrpcModule {
+    /// Create a new gRPC Module with dependency injection
+    pub fn new(
+        db_pool: PgPool,
+        patient_service: Arc<PatientService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        Self {
+            patient_directory: Arc::new(PatientDirectoryGrpc::new(patient_service, authorization_engine.clone())),
+            authorization: Arc::new(AuthorizationGrpc::new(authorization_engine, RoleService::new(db_pool))),
+            terminology: Arc::new(TerminologyGrpc::with_profile_packs()),
+        }
+    }
+
+    /// Register gRPC services at `/<package>.<Service>/<Method>`. Paths
+    /// matching no service are left to the REST router's 404.
+    pub fn routes(&self) -> Router {
+        let router = Router::new();
+        let router = Self::route(router, PatientDirectoryServer::from_arc(self.patient_directory.clone()));
+        let router = Self::route(router, AuthorizationServer::from_arc(self.authorization.clone()));
+        Self::route(router, TerminologyServer::from_arc(self.terminology.clone()))
+    }
+
+    fn route<S>(router: Router, service: S) -> Router
+    where
+        S: Service<http::Request<BoxBody>, Response = http::Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        router.route_service(
+            &format!("/{}/*rest", S::NAME),
+            service.map_request(|request: Request| request.map(tonic::body::boxed)),
+        )
+    }
+}
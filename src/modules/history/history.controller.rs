@@ -0,0 +1,376 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{AuditAction, AuditEventType, AuditLog, ResourceMeta};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource, Subject};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::history::history_service::{HistoryOperation, ResourceVersion};
+use crate::modules::history::HistoryService;
+use crate::modules::part2::{is_part2_row, Part2Consents};
+use crate::modules::security_label::labels_of;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::etag::etag;
+
+/// Serves FHIR `_history` and vread for one resource type. Reading versions
+/// takes read access to the resource, or to its patient for resources
+/// without access of their own; versions labelled beyond what the user may
+/// read, and Part 2 versions without a consent, are withheld.
+pub struct HistoryController {
+    history_service: Arc<HistoryService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    audit_service: Arc<AuditService>,
+    part2: Part2Consents,
+}
+
+#[derive(Clone)]
+pub struct HistoryState {
+    history_service: Arc<HistoryService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    audit_service: Arc<AuditService>,
+    part2: Part2Consents,
+    /// FHIR type of the resources under this router
+    resource_type: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub _count: Option<i64>,
+    pub _since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryBundle {
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    pub total: i64,
+    pub entry: Vec<HistoryBundleEntry>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryBundleEntry {
+    pub full_url: String,
+    /// Absent for deletions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<Value>,
+    pub request: HistoryEntryRequest,
+    pub response: HistoryEntryResponse,
+    pub provenance: ChangeProvenance,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryEntryRequest {
+    pub method: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntryResponse {
+    pub status: String,
+    pub etag: String,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Who made a change, when, why and from where
+#[derive(Debug, Serialize)]
+pub struct ChangeProvenance {
+    pub recorded: DateTime<Utc>,
+    /// `User/{id}`, when the change was made by an authenticated user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub who: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// `Organization/{id}` the user acted on behalf of
+    #[serde(rename = "onBehalfOf", skip_serializing_if = "Option::is_none")]
+    pub on_behalf_of: Option<String>,
+    /// System the change came from, when not this server's API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+impl HistoryController {
+    /// Create new controller with injected services
+    pub fn new(
+        history_service: Arc<HistoryService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        audit_service: Arc<AuditService>,
+        part2: Part2Consents,
+    ) -> Self {
+        Self {
+            history_service,
+            authorization_engine,
+            audit_service,
+            part2,
+        }
+    }
+
+    /// History routes for one resource type, to be merged into that
+    /// resource's router
+    pub fn routes(&self, resource_type: &'static str) -> ApiRouter {
+        ApiRouter::new()
+            .get("/:id/_history", Self::history, "All versions of a resource, newest first, as a FHIR history bundle")
+            .get("/:id/_history/:vid", Self::vread, "One version of a resource (FHIR vread)")
+            .with_state(HistoryState {
+                history_service: self.history_service.clone(),
+                authorization_engine: self.authorization_engine.clone(),
+                audit_service: self.audit_service.clone(),
+                part2: self.part2.clone(),
+                resource_type,
+            })
+    }
+
+    /// All versions of a resource, newest first, as a FHIR history bundle
+    pub async fn history(
+        State(state): State<HistoryState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Query(params): Query<HistoryQuery>,
+    ) -> Result<Json<HistoryBundle>, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Retrieving history of {}/{}", state.resource_type, id);
+
+        let history = state
+            .history_service
+            .history(state.resource_type, id, params._since, params._count)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to retrieve history of {}/{}: {}", state.resource_type, id, e);
+                Self::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to retrieve history", e.to_string())
+            })?;
+        if history.total == 0 && params._since.is_none() {
+            return Err(Self::not_found(state.resource_type, id));
+        }
+
+        let found = history.versions.len();
+        let versions = Self::readable(&state, &auth, &headers, id, history.versions).await?;
+        let withheld = found - versions.len();
+        let audit = AuditLog::new(AuditEventType::Access, AuditAction::Read, state.resource_type.to_string())
+            .with_details(format!("History: {} versions returned, {} withheld", versions.len(), withheld));
+        Self::audit(&state, &auth, id, versions.first(), audit).await?;
+
+        let entry = versions
+            .into_iter()
+            .map(|version| Self::version_to_entry(state.resource_type, id, version))
+            .collect();
+        Ok(Json(HistoryBundle {
+            resource_type: "Bundle".to_string(),
+            id: Uuid::new_v4(),
+            meta: ResourceMeta {
+                last_updated: Utc::now(),
+                ..Default::default()
+            },
+            bundle_type: "history".to_string(),
+            total: history.total - withheld as i64,
+            entry,
+        }))
+    }
+
+    /// One version of a resource (FHIR vread). A version that deleted the
+    /// resource is `410 Gone`.
+    pub async fn vread(
+        State(state): State<HistoryState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path((id, vid)): Path<(Uuid, String)>,
+    ) -> Result<([(HeaderName, String); 2], Json<Value>), (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Reading {}/{}/_history/{}", state.resource_type, id, vid);
+
+        let version = match state.history_service.version(state.resource_type, id, &vid).await {
+            Ok(Some(version)) => version,
+            Ok(None) => return Err(Self::not_found(state.resource_type, id)),
+            Err(e) => {
+                tracing::error!("Failed to read {}/{}/_history/{}: {}", state.resource_type, id, vid, e);
+                return Err(Self::error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to read version",
+                    e.to_string(),
+                ));
+            }
+        };
+        let Some(version) = Self::readable(&state, &auth, &headers, id, vec![version]).await?.pop() else {
+            return Err(Self::error(
+                StatusCode::FORBIDDEN,
+                "Access denied",
+                format!("Version {} of {}/{} is not available to you", vid, state.resource_type, id),
+            ));
+        };
+        let audit = AuditLog::new(AuditEventType::Access, AuditAction::Read, state.resource_type.to_string())
+            .with_details(format!("Version read: {}", vid));
+        Self::audit(&state, &auth, id, Some(&version), audit).await?;
+        if version.operation == HistoryOperation::Delete {
+            return Err(Self::error(
+                StatusCode::GONE,
+                "Resource deleted",
+                format!("Version {} of {}/{} is a deletion", vid, state.resource_type, id),
+            ));
+        }
+
+        let headers = [
+            (header::ETAG, etag(&version.version_id)),
+            (header::LAST_MODIFIED, version.changed_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+        ];
+        Ok((headers, Json(Self::resource_body(state.resource_type, version.resource))))
+    }
+
+    /// The versions the user may read. Read access to the resource's
+    /// authorization parent is required outright; each version is then
+    /// checked with the labels it carried, and Part 2 versions need a
+    /// consent covering the user and declared purpose, as disclosures do.
+    async fn readable(
+        state: &HistoryState,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        id: Uuid,
+        versions: Vec<ResourceVersion>,
+    ) -> Result<Vec<ResourceVersion>, (StatusCode, Json<ErrorResponse>)> {
+        let patient_id = Self::patient_of(state.resource_type, id, versions.first());
+        let parent = match (state.resource_type, patient_id) {
+            ("Patient", _) => Resource::Patient(id),
+            ("DocumentReference", _) => Resource::MedicalRecord(id),
+            ("Appointment", _) => Resource::Appointment(id),
+            (_, Some(patient_id)) => Resource::Patient(patient_id),
+            (_, None) => return Err(Self::not_found(state.resource_type, id)),
+        };
+
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), auth, headers)
+            .await
+            .map_err(Self::access_error)?;
+        authorizer.authorize(Action::Read, parent.clone()).await.map_err(Self::access_error)?;
+        let mut versions = authorizer
+            .retain_labelled(Action::Read, versions, |_| parent.clone(), |version| {
+                labels_of(&version.resource["meta"]["security"])
+            })
+            .await
+            .map_err(Self::access_error)?;
+
+        if let Some(patient_id) = patient_id {
+            let recipient = Subject::User(auth.user_id).to_string();
+            let disclosure = state
+                .part2
+                .disclosure(patient_id, Some(&recipient), auth.purpose_of_use)
+                .await
+                .map_err(Self::access_error)?;
+            versions.retain(|version| disclosure.discloses(is_part2_row(&version.resource)));
+        }
+        Ok(versions)
+    }
+
+    /// Patient a resource's versions belong to
+    fn patient_of(resource_type: &str, id: Uuid, version: Option<&ResourceVersion>) -> Option<Uuid> {
+        if resource_type == "Patient" {
+            return Some(id);
+        }
+        version?.resource["patient_id"].as_str()?.parse().ok()
+    }
+
+    /// Log the user's read of a resource's versions
+    async fn audit(
+        state: &HistoryState,
+        auth: &AuthContext,
+        id: Uuid,
+        version: Option<&ResourceVersion>,
+        audit: AuditLog,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let mut audit = auth.audit(audit).with_resource(id);
+        if let Some(patient_id) = Self::patient_of(state.resource_type, id, version) {
+            audit = audit.with_patient(patient_id);
+        }
+        state.audit_service.create_audit_log(&audit).await.map_err(|e| {
+            tracing::error!("Failed to audit history read of {}/{}: {}", state.resource_type, id, e);
+            Self::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to record access", e.to_string())
+        })?;
+        Ok(())
+    }
+
+    fn access_error(e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        match e {
+            HimsError::SecurityError { message } => Self::error(StatusCode::FORBIDDEN, "Access denied", message),
+            e => {
+                tracing::error!("Authorization check failed: {}", e);
+                Self::error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Authorization error",
+                    "Failed to check authorization".to_string(),
+                )
+            }
+        }
+    }
+
+    fn version_to_entry(resource_type: &str, id: Uuid, version: ResourceVersion) -> HistoryBundleEntry {
+        let (url, status) = match version.operation {
+            HistoryOperation::Create => (resource_type.to_string(), "201 Created"),
+            HistoryOperation::Update => (format!("{}/{}", resource_type, id), "200 OK"),
+            HistoryOperation::Delete => (format!("{}/{}", resource_type, id), "204 No Content"),
+        };
+
+        HistoryBundleEntry {
+            full_url: format!("{}/{}/_history/{}", resource_type, id, version.version_id),
+            resource: (version.operation != HistoryOperation::Delete)
+                .then(|| Self::resource_body(resource_type, version.resource)),
+            request: HistoryEntryRequest {
+                method: version.operation.method().to_string(),
+                url,
+            },
+            response: HistoryEntryResponse {
+                status: status.to_string(),
+                etag: etag(&version.version_id),
+                last_modified: version.changed_at,
+            },
+            provenance: ChangeProvenance {
+                recorded: version.changed_at,
+                who: version.changed_by.map(|user_id| format!("User/{}", user_id)),
+                reason: version.change_reason,
+                on_behalf_of: version.on_behalf_of.map(|organization_id| format!("Organization/{}", organization_id)),
+                source: version.source_system,
+            },
+        }
+    }
+
+    /// The stored resource, tagged with its FHIR type
+    fn resource_body(resource_type: &str, mut resource: Value) -> Value {
+        if let Value::Object(fields) = &mut resource {
+            fields.insert("resourceType".to_string(), Value::String(resource_type.to_string()));
+        }
+        resource
+    }
+
+    fn not_found(resource_type: &str, id: Uuid) -> (StatusCode, Json<ErrorResponse>) {
+        Self::error(
+            StatusCode::NOT_FOUND,
+            "Resource not found",
+            format!("No history for {}/{}", resource_type, id),
+        )
+    }
+
+    fn error(status: StatusCode, error: &str, message: String) -> (StatusCode, Json<ErrorResponse>) {
+        (
+            status,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message,
+            }),
+        )
+    }
+}
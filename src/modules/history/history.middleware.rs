@@ -0,0 +1,46 @@
+use axum::{extract::Request, http::HeaderMap, middleware::Next, response::Response};
+
+use crate::database::provenance::{with_change_context, ChangeContext};
+use crate::modules::auth::AuthContext;
+
+/// Header carrying a free-text reason for the request's changes, recorded
+/// in resource history
+pub const CHANGE_REASON_HEADER: &str = "x-change-reason";
+
+/// Header naming the system a request's changes came from, such as an
+/// interface engine relaying another hospital's records
+pub const SOURCE_SYSTEM_HEADER: &str = "x-source-system";
+
+/// Longest change reason recorded; longer reasons are truncated
+const MAX_CHANGE_REASON_CHARS: usize = 1024;
+
+/// Longest source system recorded
+const MAX_SOURCE_SYSTEM_CHARS: usize = 255;
+
+/// Change provenance middleware
+pub struct HistoryMiddleware;
+
+impl HistoryMiddleware {
+    /// Run the request with its user, change reason and source system as
+    /// the change context, so any resource versions it writes record who,
+    /// why and from where. Unauthenticated requests are still served; their
+    /// versions carry no user.
+    pub async fn change_context(headers: HeaderMap, request: Request, next: Next) -> Response {
+        let user_id = request.extensions().get::<AuthContext>().map(|auth| auth.user_id);
+        let reason = Self::header(&headers, CHANGE_REASON_HEADER, MAX_CHANGE_REASON_CHARS);
+        let mut context = ChangeContext::new(user_id, reason);
+        context.source = Self::header(&headers, SOURCE_SYSTEM_HEADER, MAX_SOURCE_SYSTEM_CHARS);
+
+        with_change_context(context, next.run(request)).await
+    }
+
+    /// A header's trimmed text, truncated to `max_chars`
+    fn header(headers: &HeaderMap, name: &str, max_chars: usize) -> Option<String> {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(max_chars).collect())
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+
+// Import SQL queries from separate file
+use crate::modules::history::history_sql::*;
+
+/// Versions returned by a history request unless `_count` says otherwise
+pub const DEFAULT_HISTORY_COUNT: i64 = 50;
+
+/// Most versions one history request returns
+pub const MAX_HISTORY_COUNT: i64 = 500;
+
+/// Change that produced a version
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+impl HistoryOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryOperation::Create => "create",
+            HistoryOperation::Update => "update",
+            HistoryOperation::Delete => "delete",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "create" => HistoryOperation::Create,
+            "delete" => HistoryOperation::Delete,
+            _ => HistoryOperation::Update,
+        }
+    }
+
+    /// HTTP method of the interaction, as FHIR history bundles report it
+    pub fn method(&self) -> &'static str {
+        match self {
+            HistoryOperation::Create => "POST",
+            HistoryOperation::Update => "PUT",
+            HistoryOperation::Delete => "DELETE",
+        }
+    }
+}
+
+/// One stored version of a resource with its change provenance
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceVersion {
+    pub version_id: String,
+    pub operation: HistoryOperation,
+    /// The resource row as stored at this version
+    pub resource: Value,
+    pub changed_by: Option<Uuid>,
+    pub changed_at: DateTime<Utc>,
+    pub change_reason: Option<String>,
+    /// Organization the user acted on behalf of
+    pub on_behalf_of: Option<Uuid>,
+    /// System the change came from, when not this server's API
+    pub source_system: Option<String>,
+}
+
+/// A page of a resource's history
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceHistory {
+    pub total: i64,
+    /// Newest first
+    pub versions: Vec<ResourceVersion>,
+}
+
+/// Reads the version history recorded for clinical resources
+#[derive(Debug, Clone)]
+pub struct HistoryService {
+    pool: PgPool,
+}
+
+impl HistoryService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Versions of a resource, newest first. `resource_type` is the FHIR type.
+    pub async fn history(
+        &self,
+        resource_type: &str,
+        id: Uuid,
+        since: Option<DateTime<Utc>>,
+        count: Option<i64>,
+    ) -> Result<ResourceHistory, HimsError> {
+        let count = count.unwrap_or(DEFAULT_HISTORY_COUNT).clamp(1, MAX_HISTORY_COUNT);
+
+        let total: i64 = sqlx::query_scalar(COUNT_RESOURCE_HISTORY)
+            .bind(resource_type)
+            .bind(id)
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let versions = sqlx::query(GET_RESOURCE_HISTORY)
+            .bind(resource_type)
+            .bind(id)
+            .bind(since)
+            .bind(count)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_version)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ResourceHistory { total, versions })
+    }
+
+    /// One version of a resource
+    pub async fn version(
+        &self,
+        resource_type: &str,
+        id: Uuid,
+        version_id: &str,
+    ) -> Result<Option<ResourceVersion>, HimsError> {
+        sqlx::query(GET_RESOURCE_VERSION)
+            .bind(resource_type)
+            .bind(id)
+            .bind(version_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_version)
+            .transpose()
+    }
+
+    fn row_to_version(row: &PgRow) -> Result<ResourceVersion, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        Ok(ResourceVersion {
+            version_id: row.try_get("version_id").map_err(read)?,
+            operation: HistoryOperation::from_string(&row.try_get::<String, _>("operation").map_err(read)?),
+            resource: row.try_get("resource").map_err(read)?,
+            changed_by: row.try_get("changed_by").map_err(read)?,
+            changed_at: row.try_get("changed_at").map_err(read)?,
+            change_reason: row.try_get("change_reason").map_err(read)?,
+            on_behalf_of: row.try_get("on_behalf_of").map_err(read)?,
+            source_system: row.try_get("source_system").map_err(read)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_round_trip() {
+        for operation in [HistoryOperation::Create, HistoryOperation::Update, HistoryOperation::Delete] {
+            assert_eq!(HistoryOperation::from_string(operation.as_str()), operation);
+        }
+        assert_eq!(HistoryOperation::Delete.method(), "DELETE");
+    }
+}
@@ -0,0 +1,32 @@
+//! Resource History SQL Queries
+//!
+//! This file contains all SQL queries used by the history service
+//! for clean separation of concerns and better maintainability.
+//! Rows are written by the `record_resource_history` trigger, never here.
+
+/// Versions of a resource, newest first
+pub const GET_RESOURCE_HISTORY: &str = r#"
+    SELECT version_id, operation, resource, changed_by, changed_at, change_reason, on_behalf_of, source_system
+    FROM resource_history
+    WHERE resource_type = $1 AND resource_id = $2
+        AND ($3::timestamptz IS NULL OR changed_at >= $3)
+    ORDER BY id DESC
+    LIMIT $4
+"#;
+
+/// Number of versions of a resource
+pub const COUNT_RESOURCE_HISTORY: &str = r#"
+    SELECT COUNT(*)
+    FROM resource_history
+    WHERE resource_type = $1 AND resource_id = $2
+        AND ($3::timestamptz IS NULL OR changed_at >= $3)
+"#;
+
+/// One version of a resource
+pub const GET_RESOURCE_VERSION: &str = r#"
+    SELECT version_id, operation, resource, changed_by, changed_at, change_reason, on_behalf_of, source_system
+    FROM resource_history
+    WHERE resource_type = $1 AND resource_id = $2 AND version_id = $3
+    ORDER BY id DESC
+    LIMIT 1
+"#;
@@ -0,0 +1,64 @@
+//! History Module
+//! 
+//! This module keeps every version of clinical resources including:
+//! - Append-only resource history written by database trigger on each new version
+//! - Change provenance (user, organization, time, `X-Change-Reason`, `X-Source-System`) for medico-legal review
+//! - FHIR `GET /{type}/{id}/_history` bundles and `GET /{type}/{id}/_history/{vid}` vread,
+//!   for users who may read the resource, with labelled and Part 2 versions withheld and each read audited
+
+#[path = "history.controller.rs"]
+pub mod history_controller;
+#[path = "history.service.rs"]
+pub mod history_service;
+#[path = "history.middleware.rs"]
+pub mod history_middleware;
+#[path = "history.sql.rs"]
+pub mod history_sql;
+
+pub use history_controller::HistoryController;
+pub use history_service::{HistoryOperation, HistoryService, ResourceHistory, ResourceVersion};
+pub use history_middleware::HistoryMiddleware;
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::audit::AuditService;
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::part2::Part2Consents;
+use crate::utils::api_router::ApiRouter;
+
+/// History Module Configuration
+pub struct HistoryModule {
+    pub service: Arc<HistoryService>,
+    pub controller: Arc<HistoryController>,
+}
+
+impl HistoryModule {
+    /// Create a new History Module with dependency injection; versions are
+    /// served to those `authorization_engine` lets read the resource
+    pub fn new(db_pool: PgPool, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        let service = Arc::new(HistoryService::new(db_pool.clone()));
+        let controller = Arc::new(HistoryController::new(
+            service.clone(),
+            authorization_engine,
+            Arc::new(AuditService::new(db_pool.clone())),
+            Part2Consents::new(db_pool),
+        ));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register history routes for a FHIR resource type, to be merged into
+    /// that resource's router
+    pub fn routes(&self, resource_type: &'static str) -> ApiRouter {
+        self.controller.routes(resource_type)
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<HistoryService> {
+        self.service.clone()
+    }
+}
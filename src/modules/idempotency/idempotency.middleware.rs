@@ -0,0 +1,150 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::core::HimsError;
+use crate::modules::auth::SessionClient;
+use crate::modules::idempotency::idempotency_service::{request_hash, Claim, IdempotencyKey, StoredResponse};
+use crate::modules::idempotency::IdempotencyService;
+
+/// Header naming a request so retries of it are recognised
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Set on responses sent again for a retried request
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Idempotency key middleware
+pub struct IdempotencyMiddleware {
+    service: Arc<IdempotencyService>,
+}
+
+impl IdempotencyMiddleware {
+    pub fn new(service: Arc<IdempotencyService>) -> Self {
+        Self { service }
+    }
+
+    pub fn service(&self) -> Arc<IdempotencyService> {
+        self.service.clone()
+    }
+
+    /// Run a POST, PUT or PATCH carrying an `Idempotency-Key` once. A retry
+    /// with the same key and request gets the recorded response; the same
+    /// key with a different request is a 422, and a retry while the first
+    /// request is still running a 409. Other requests pass through.
+    /// Anonymous requests' keys are scoped to the client IP the rate
+    /// limiter resolved.
+    pub async fn guard(
+        State(service): State<Arc<IdempotencyService>>,
+        headers: HeaderMap,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        if !matches!(*request.method(), Method::POST | Method::PUT | Method::PATCH) {
+            return next.run(request).await;
+        }
+        let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+            return next.run(request).await;
+        };
+
+        let client_ip = SessionClient::from_request(request.extensions(), &headers).ip_address;
+        let key = match value
+            .to_str()
+            .map_err(|_| HimsError::ValidationError {
+                message: "Idempotency-Key must be visible ASCII".to_string(),
+            })
+            .and_then(|value| IdempotencyKey::from_headers(value, &headers, client_ip.as_deref()))
+        {
+            Ok(key) => key,
+            Err(e) => return Self::error(StatusCode::BAD_REQUEST, "Invalid idempotency key", e.to_string()),
+        };
+
+        let (parts, body) = request.into_parts();
+        let body = match to_bytes(body, service.config().max_request_bytes).await {
+            Ok(body) => body,
+            Err(_) => {
+                return Self::error(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "Request too large",
+                    "Request body is too large to be made idempotent".to_string(),
+                )
+            }
+        };
+        let method = parts.method.to_string();
+        let path = parts.uri.path_and_query().map_or_else(|| parts.uri.path(), |path| path.as_str()).to_string();
+        let hash = request_hash(&method, &path, &body);
+
+        match service.claim(&key, &method, &path, &hash).await {
+            Ok(Claim::Acquired) => {}
+            Ok(Claim::Replay(stored)) => {
+                tracing::info!("Replaying response for idempotency key {}", key.key);
+                return Self::replay(stored);
+            }
+            Ok(Claim::InProgress) => {
+                return Self::error(
+                    StatusCode::CONFLICT,
+                    "Request in progress",
+                    "A request with this Idempotency-Key is still being processed".to_string(),
+                )
+            }
+            Ok(Claim::Mismatch) => {
+                tracing::warn!("Idempotency key {} reused for a different request", key.key);
+                return Self::error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Idempotency key reused",
+                    "This Idempotency-Key was used with a different request".to_string(),
+                );
+            }
+            Err(e) => {
+                // Running the request unguarded could create the duplicate the client is avoiding
+                tracing::error!("Idempotency key claim failed: {}", e);
+                return Self::error(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Idempotency unavailable",
+                    "The request could not be made idempotent; retry with the same key".to_string(),
+                );
+            }
+        }
+
+        let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+        let (parts, body) = response.into_parts();
+        let body = match to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!("Failed to read response for idempotency key {}: {}", key.key, e);
+                if let Err(e) = service.release(&key).await {
+                    tracing::error!("Failed to release idempotency key {}: {}", key.key, e);
+                }
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+        let stored = StoredResponse {
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body: body.to_vec(),
+        };
+        if let Err(e) = service.complete(&key, &stored).await {
+            tracing::error!("Failed to record response for idempotency key {}: {}", key.key, e);
+        }
+        Response::from_parts(parts, Body::from(body))
+    }
+
+    fn replay(stored: StoredResponse) -> Response {
+        let mut response = (stored.status, stored.headers, stored.body).into_response();
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+
+    fn error(status: StatusCode, error: &str, message: String) -> Response {
+        (status, Json(json!({ "error": error, "message": message }))).into_response()
+    }
+}
@@ -0,0 +1,331 @@
+use async_trait::async_trait;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use chrono::{Duration as ChronoDuration, Utc};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+
+use crate::core::HimsError;
+use crate::modules::scheduler::ScheduledJob;
+use crate::modules::service_account::service_account_middleware::API_KEY_HEADER;
+use crate::modules::service_account::service_account_service::key_prefix;
+use crate::utils::auth::extract_user_from_headers;
+
+// Import SQL queries from separate file
+use crate::modules::idempotency::idempotency_sql::*;
+
+/// Response headers replayed with a stored response; others, such as
+/// correlation IDs, belong to the request that produced them
+const REPLAYED_HEADERS: [HeaderName; 5] = [
+    header::CONTENT_TYPE,
+    header::LOCATION,
+    header::ETAG,
+    header::LAST_MODIFIED,
+    header::CONTENT_LOCATION,
+];
+
+/// Idempotency settings
+#[derive(Debug, Clone)]
+pub struct IdempotencyConfig {
+    /// How long a key and its response are kept
+    pub ttl: ChronoDuration,
+    /// How long a request may hold a key without recording a response
+    /// before a retry may take it over, e.g. after the server restarted
+    pub lock_timeout: ChronoDuration,
+    /// Largest request body accepted with a key
+    pub max_request_bytes: usize,
+    /// Largest response stored; larger responses release their key
+    pub max_response_bytes: usize,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl: ChronoDuration::hours(24),
+            lock_timeout: ChronoDuration::minutes(5),
+            max_request_bytes: 2 * 1024 * 1024,
+            max_response_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// A response recorded for a key
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// Outcome of presenting a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Claim {
+    /// The key is new; run the request and record its response
+    Acquired,
+    /// The request was already made; send its response again
+    Replay(StoredResponse),
+    /// The first request with the key is still running
+    InProgress,
+    /// The key was used for a different request
+    Mismatch,
+}
+
+/// One client's use of one key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey {
+    /// Who sent the key; keys from different clients never collide
+    pub scope: String,
+    pub key: String,
+}
+
+impl IdempotencyKey {
+    pub const MAX_LENGTH: usize = 255;
+
+    /// Key for a request's `Idempotency-Key` value, scoped to the API key,
+    /// user or else client IP sending it. The IP is the one the rate
+    /// limiter resolved, as forwarding headers can be forged.
+    pub fn from_headers(value: &str, headers: &HeaderMap, client_ip: Option<&str>) -> Result<Self, HimsError> {
+        let valid = !value.is_empty()
+            && value.len() <= Self::MAX_LENGTH
+            && value.chars().all(|c| c.is_ascii_graphic());
+        if !valid {
+            return Err(HimsError::ValidationError {
+                message: format!(
+                    "Idempotency-Key must be 1-{} visible ASCII characters",
+                    Self::MAX_LENGTH
+                ),
+            });
+        }
+
+        let api_key = headers
+            .get(API_KEY_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .and_then(key_prefix);
+        let scope = match (api_key, extract_user_from_headers(headers).ok(), client_ip) {
+            (Some(prefix), _, _) => format!("key:{}", prefix),
+            (None, Some(user_id), _) => format!("user:{}", user_id),
+            (None, None, Some(ip)) => format!("ip:{}", ip),
+            (None, None, None) => "anonymous".to_string(),
+        };
+
+        Ok(Self {
+            scope,
+            key: value.to_string(),
+        })
+    }
+}
+
+/// Hex SHA-256 identifying a request: method, path with query, and body
+pub fn request_hash(method: &str, path_and_query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update([0]);
+    hasher.update(path_and_query.as_bytes());
+    hasher.update([0]);
+    hasher.update(body);
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Request idempotency keys: the first POST, PUT or PATCH with a key runs,
+/// and retries with the same key get its response back
+pub struct IdempotencyService {
+    pool: PgPool,
+    config: IdempotencyConfig,
+}
+
+impl IdempotencyService {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_config(pool, IdempotencyConfig::default())
+    }
+
+    pub fn with_config(pool: PgPool, config: IdempotencyConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub fn config(&self) -> &IdempotencyConfig {
+        &self.config
+    }
+
+    /// Claim a key for a request, or find what became of the request that
+    /// claimed it first
+    pub async fn claim(&self, key: &IdempotencyKey, method: &str, path: &str, request_hash: &str) -> Result<Claim, HimsError> {
+        let now = Utc::now();
+        let claimed = sqlx::query(CLAIM_IDEMPOTENCY_KEY)
+            .bind(&key.scope)
+            .bind(&key.key)
+            .bind(method)
+            .bind(path)
+            .bind(request_hash)
+            .bind(now + self.config.ttl)
+            .bind(now - self.config.lock_timeout)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        if claimed.is_some() {
+            return Ok(Claim::Acquired);
+        }
+
+        let row = sqlx::query(GET_IDEMPOTENCY_KEY)
+            .bind(&key.scope)
+            .bind(&key.key)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        // Released between the two queries; the client may retry
+        let Some(row) = row else {
+            return Ok(Claim::InProgress);
+        };
+
+        if row.get::<String, _>("request_hash") != request_hash {
+            return Ok(Claim::Mismatch);
+        }
+        let Some(status) = row.get::<Option<i32>, _>("response_status") else {
+            return Ok(Claim::InProgress);
+        };
+
+        Ok(Claim::Replay(StoredResponse {
+            status: StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK),
+            headers: Self::headers_from_json(&row.get("response_headers")),
+            body: row.get::<Option<Vec<u8>>, _>("response_body").unwrap_or_default(),
+        }))
+    }
+
+    /// Record the response to a claimed key. Server errors are not
+    /// recorded but release the key, so a retry runs the request again.
+    pub async fn complete(&self, key: &IdempotencyKey, response: &StoredResponse) -> Result<(), HimsError> {
+        if response.status.is_server_error() {
+            return self.release(key).await;
+        }
+        if response.body.len() > self.config.max_response_bytes {
+            tracing::warn!(
+                "Response for idempotency key {} is {} bytes, too large to store",
+                key.key,
+                response.body.len()
+            );
+            return self.release(key).await;
+        }
+
+        sqlx::query(COMPLETE_IDEMPOTENCY_KEY)
+            .bind(&key.scope)
+            .bind(&key.key)
+            .bind(response.status.as_u16() as i32)
+            .bind(Self::headers_to_json(&response.headers))
+            .bind(&response.body)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Forget a key, as if it had never been sent
+    pub async fn release(&self, key: &IdempotencyKey) -> Result<(), HimsError> {
+        sqlx::query(RELEASE_IDEMPOTENCY_KEY)
+            .bind(&key.scope)
+            .bind(&key.key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Delete expired keys of every tenant
+    pub async fn purge_expired(&self) -> Result<u64, HimsError> {
+        let result = sqlx::query(PURGE_EXPIRED_IDEMPOTENCY_KEYS)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(result.rows_affected())
+    }
+
+    fn headers_to_json(headers: &HeaderMap) -> Value {
+        let mut stored = Map::new();
+        for name in REPLAYED_HEADERS.iter() {
+            if let Some(value) = headers.get(name).and_then(|value| value.to_str().ok()) {
+                stored.insert(name.as_str().to_string(), Value::String(value.to_string()));
+            }
+        }
+        Value::Object(stored)
+    }
+
+    fn headers_from_json(stored: &Value) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in stored.as_object().into_iter().flatten() {
+            let parsed = (HeaderName::try_from(name.as_str()), value.as_str().map(HeaderValue::from_str));
+            if let (Ok(name), Some(Ok(value))) = parsed {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+}
+
+#[async_trait]
+impl ScheduledJob for IdempotencyService {
+    /// Forget keys whose TTL has passed
+    async fn run(&self) -> Result<Value, HimsError> {
+        Ok(json!({ "purged": self.purge_expired().await? }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::auth::auth_jwt::test_tokens;
+    use crate::modules::auth::AuthenticatedUser;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_request_hash() {
+        let hash = request_hash("POST", "/api/v1/patients", b"{\"name\":[]}");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, request_hash("POST", "/api/v1/patients", b"{\"name\":[]}"));
+        assert_ne!(hash, request_hash("PUT", "/api/v1/patients", b"{\"name\":[]}"));
+        assert_ne!(hash, request_hash("POST", "/api/v1/patients?x=1", b"{\"name\":[]}"));
+        assert_ne!(hash, request_hash("POST", "/api/v1/patients", b"{\"name\":[1]}"));
+    }
+
+    #[test]
+    fn test_key_scope() {
+        let user_id = Uuid::new_v4();
+        let tokens = test_tokens(&AuthenticatedUser {
+            id: user_id.to_string(),
+            username: "clerk".to_string(),
+            role: "receptionist".to_string(),
+            permissions: vec![],
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {}", tokens.access_token).parse().unwrap());
+        let key = IdempotencyKey::from_headers("retry-7f3a", &headers, Some("203.0.113.9")).unwrap();
+        assert_eq!(key.scope, format!("user:{}", user_id));
+        assert_eq!(key.key, "retry-7f3a");
+
+        // Forwarding headers do not choose an anonymous client's scope
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.9, 10.0.0.1".parse().unwrap());
+        assert_eq!(IdempotencyKey::from_headers("k", &headers, Some("192.0.2.50")).unwrap().scope, "ip:192.0.2.50");
+        assert_eq!(IdempotencyKey::from_headers("k", &headers, None).unwrap().scope, "anonymous");
+    }
+
+    #[test]
+    fn test_key_validation() {
+        let headers = HeaderMap::new();
+        assert!(IdempotencyKey::from_headers("", &headers, None).is_err());
+        assert!(IdempotencyKey::from_headers("has space", &headers, None).is_err());
+        assert!(IdempotencyKey::from_headers(&"k".repeat(256), &headers, None).is_err());
+        assert!(IdempotencyKey::from_headers(&"k".repeat(255), &headers, None).is_ok());
+    }
+
+    #[test]
+    fn test_stored_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/fhir+json".parse().unwrap());
+        headers.insert(header::ETAG, "W/\"1\"".parse().unwrap());
+        headers.insert("x-correlation-id", "abc".parse().unwrap());
+
+        let stored = IdempotencyService::headers_to_json(&headers);
+        let restored = IdempotencyService::headers_from_json(&stored);
+        assert_eq!(restored.get(header::CONTENT_TYPE).unwrap(), "application/fhir+json");
+        assert_eq!(restored.get(header::ETAG).unwrap(), "W/\"1\"");
+        assert!(restored.get("x-correlation-id").is_none());
+    }
+}
@@ -0,0 +1,44 @@
+//! Idempotency SQL Queries
+//!
+//! This file contains all SQL queries used by the idempotency module
+//! for clean separation of concerns and better maintainability.
+
+/// Claim a key for a request. An existing row is only taken over once it
+/// has expired, or when its request stopped without recording a response
+/// ($7 is the cutoff for that). Returns no row when the key is held.
+pub const CLAIM_IDEMPOTENCY_KEY: &str = r#"
+    INSERT INTO idempotency_keys (scope, idempotency_key, method, path, request_hash, expires_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (tenant_id, scope, idempotency_key) DO UPDATE
+    SET method = EXCLUDED.method,
+        path = EXCLUDED.path,
+        request_hash = EXCLUDED.request_hash,
+        response_status = NULL,
+        response_headers = '{}',
+        response_body = NULL,
+        created_at = NOW(),
+        expires_at = EXCLUDED.expires_at
+    WHERE idempotency_keys.expires_at < NOW()
+       OR (idempotency_keys.response_status IS NULL AND idempotency_keys.created_at < $7)
+    RETURNING idempotency_key
+"#;
+
+pub const GET_IDEMPOTENCY_KEY: &str = r#"
+    SELECT request_hash, response_status, response_headers, response_body
+    FROM idempotency_keys
+    WHERE scope = $1 AND idempotency_key = $2
+"#;
+
+pub const COMPLETE_IDEMPOTENCY_KEY: &str = r#"
+    UPDATE idempotency_keys
+    SET response_status = $3, response_headers = $4, response_body = $5
+    WHERE scope = $1 AND idempotency_key = $2
+"#;
+
+pub const RELEASE_IDEMPOTENCY_KEY: &str = r#"
+    DELETE FROM idempotency_keys WHERE scope = $1 AND idempotency_key = $2
+"#;
+
+pub const PURGE_EXPIRED_IDEMPOTENCY_KEYS: &str = r#"
+    DELETE FROM idempotency_keys WHERE expires_at < NOW()
+"#;
@@ -0,0 +1,41 @@
+//! Idempotency Module
+//!
+//! This module makes mutating requests safe to retry including:
+//! - An `Idempotency-Key` header on any POST, PUT or PATCH
+//! - The first response stored per client and key, replayed for retries
+//! - Keys reused with a different request rejected
+//! - Keys expiring after a TTL and purged in the background
+
+#[path = "idempotency.service.rs"]
+pub mod idempotency_service;
+#[path = "idempotency.middleware.rs"]
+pub mod idempotency_middleware;
+#[path = "idempotency.sql.rs"]
+pub mod idempotency_sql;
+
+pub use idempotency_middleware::{IdempotencyMiddleware, IDEMPOTENCY_KEY_HEADER};
+pub use idempotency_service::{IdempotencyConfig, IdempotencyService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Idempotency Module Configuration
+pub struct IdempotencyModule {
+    pub service: Arc<IdempotencyService>,
+    pub middleware: Arc<IdempotencyMiddleware>,
+}
+
+impl IdempotencyModule {
+    /// Create a new Idempotency Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(IdempotencyService::new(db_pool));
+        let middleware = Arc::new(IdempotencyMiddleware::new(service.clone()));
+
+        Self { service, middleware }
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<IdempotencyService> {
+        self.service.clone()
+    }
+}
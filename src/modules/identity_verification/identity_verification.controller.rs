@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::identity_verification::identity_verification_service::{
+    IdentityVerification, RequestSource, VerifyRequest,
+};
+use crate::modules::identity_verification::IdentityVerificationService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Identity verification controller for checking patients' demographics
+/// against their ABHA or Aadhaar at registration
+pub struct IdentityVerificationController {
+    verification_service: Arc<IdentityVerificationService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl IdentityVerificationController {
+    /// Create new controller with injected service
+    pub fn new(verification_service: Arc<IdentityVerificationService>) -> Self {
+        Self { verification_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/verifications", Self::verify, "Verify a patient's demographics against their ABHA or Aadhaar")
+            .get("/verifications/:id", Self::get, "Get a demographic verification")
+            .get(
+                "/patients/:patient_id/verifications",
+                Self::list_for_patient,
+                "List a patient's demographic verifications",
+            )
+            .with_state(self.verification_service.clone())
+    }
+
+    /// Verify a patient's demographics, with their consent
+    pub async fn verify(
+        State(service): State<Arc<IdentityVerificationService>>,
+        auth: AuthContext,
+        Json(payload): Json<VerifyRequest>,
+    ) -> Result<(StatusCode, Json<IdentityVerification>), ErrorReply> {
+        match service.verify(payload, auth.user_id, Self::source(&auth)).await {
+            Ok(verification) => Ok((StatusCode::CREATED, Json(verification))),
+            Err(e) => Err(Self::error_response("Failed to verify demographics", e)),
+        }
+    }
+
+    /// Get a demographic verification
+    pub async fn get(
+        State(service): State<Arc<IdentityVerificationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<IdentityVerification>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.get(id, actor).await {
+            Ok(Some(verification)) => Ok(Json(verification)),
+            Ok(None) => Err(Self::not_found("Verification", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve verification", e)),
+        }
+    }
+
+    /// List a patient's demographic verifications, latest first
+    pub async fn list_for_patient(
+        State(service): State<Arc<IdentityVerificationService>>,
+        headers: HeaderMap,
+        Path(patient_id): Path<Uuid>,
+    ) -> Result<Json<Vec<IdentityVerification>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_for_patient(patient_id, actor).await {
+            Ok(verifications) => Ok(Json(verifications)),
+            Err(e) => Err(Self::error_response("Failed to list verifications", e)),
+        }
+    }
+
+    /// Where the request came from, for the audit trail: the connection's
+    /// address, or the client a trusted proxy forwarded, never a header a
+    /// client could set
+    fn source(auth: &AuthContext) -> RequestSource {
+        RequestSource {
+            ip_address: auth.source_ip.clone(),
+            user_agent: auth.user_agent.clone(),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(what: &str, id: Uuid) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", what),
+                message: format!("{} with id {} not found", what, id),
+            }),
+        )
+    }
+
+    /// Map service errors to HTTP status codes; the authority failing to
+    /// answer is a bad gateway, and verification not being set up a 503
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::NetworkError { .. } | HimsError::AuthenticationError { .. } => StatusCode::BAD_GATEWAY,
+            HimsError::ConfigurationError { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
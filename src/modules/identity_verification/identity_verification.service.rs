@@ -0,0 +1,340 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::countries::india::verification::{
+    AbdmVerificationConfig, AbdmVerifier, DemographicVerificationRequest, DemographicVerifier, IdentityDocument,
+    VerificationOutcome, VerificationResult,
+};
+use crate::models::{AuditAction, AuditEventType, AuditLog, AuditOutcome};
+use crate::modules::audit::AuditService;
+use crate::modules::authorization::Action;
+use crate::modules::role::RoleService;
+
+// Import SQL queries
+use crate::modules::identity_verification::identity_verification_sql::*;
+
+/// Identity verification settings
+#[derive(Debug, Clone)]
+pub struct IdentityVerificationConfig {
+    /// ABDM sandbox and UIDAI gateway demographics are verified with
+    pub abdm: Option<AbdmVerificationConfig>,
+    /// Hours an answer is reused for the same demographics before the
+    /// authority is asked again
+    pub cache_hours: i64,
+}
+
+impl Default for IdentityVerificationConfig {
+    fn default() -> Self {
+        Self {
+            abdm: None,
+            cache_hours: 24,
+        }
+    }
+}
+
+impl IdentityVerificationConfig {
+    /// Settings from the ABDM credentials and
+    /// `IDENTITY_VERIFICATION_CACHE_HOURS`, up to 30 days; 0 turns caching off
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            abdm: AbdmVerificationConfig::from_env(),
+            cache_hours: var("IDENTITY_VERIFICATION_CACHE_HOURS")
+                .and_then(|hours| hours.parse().ok())
+                .filter(|hours| (0..=720).contains(hours))
+                .unwrap_or(Self::default().cache_hours),
+        }
+    }
+}
+
+/// How the patient consented, or refused, to verification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationConsent {
+    pub granted: bool,
+    /// How consent was taken, such as verbal, written or biometric
+    pub method: String,
+    /// Language the purpose of verification was explained in
+    pub language: Option<String>,
+    /// Who consented, if not the patient, such as a parent or guardian
+    pub consenter: Option<String>,
+}
+
+/// Demographics to verify, and the patient's consent to verifying them
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerifyRequest {
+    /// The registered patient, if registration has got that far
+    pub patient_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub demographics: DemographicVerificationRequest,
+    pub consent: VerificationConsent,
+}
+
+/// Where a verification was asked for from, for the audit trail
+#[derive(Debug, Clone, Default)]
+pub struct RequestSource {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// A verification of a patient's demographics against their ID
+#[derive(Debug, Clone, Serialize)]
+pub struct IdentityVerification {
+    pub id: Uuid,
+    pub patient_id: Option<Uuid>,
+    pub document: IdentityDocument,
+    /// The ID number with all but its last four digits hidden
+    pub masked_number: String,
+    pub outcome: VerificationOutcome,
+    pub reference: Option<String>,
+    pub detail: Option<String>,
+    /// The answer was an earlier one for the same demographics
+    pub cached: bool,
+    pub consent_id: Uuid,
+    pub verified_by: Uuid,
+    pub verified_at: DateTime<Utc>,
+    /// Until when the answer is reused
+    pub expires_at: DateTime<Utc>,
+}
+
+fn verification_from_row(row: &PgRow) -> IdentityVerification {
+    IdentityVerification {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        document: IdentityDocument::from_string(row.get("document")).unwrap_or(IdentityDocument::Abha),
+        masked_number: row.get("masked_number"),
+        outcome: VerificationOutcome::from_string(row.get("outcome")).unwrap_or(VerificationOutcome::Mismatch),
+        reference: row.get("reference"),
+        detail: row.get("detail"),
+        cached: row.get("cached"),
+        consent_id: row.get("consent_id"),
+        verified_by: row.get("verified_by"),
+        verified_at: row.get("verified_at"),
+        expires_at: row.get("expires_at"),
+    }
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Identity verification service: patients' demographics verified against
+/// their ABHA or Aadhaar once they consent, answers reused for a while, and
+/// every verification audited before and after the authority is called
+pub struct IdentityVerificationService {
+    pool: PgPool,
+    role_service: RoleService,
+    audit_service: AuditService,
+    verifier: Option<Arc<dyn DemographicVerifier>>,
+    config: IdentityVerificationConfig,
+}
+
+impl IdentityVerificationService {
+    /// Create new identity verification service
+    pub fn new(pool: PgPool, config: IdentityVerificationConfig) -> Self {
+        let verifier = config.abdm.clone().and_then(|abdm| match AbdmVerifier::new(abdm) {
+            Ok(verifier) => Some(Arc::new(verifier) as Arc<dyn DemographicVerifier>),
+            Err(e) => {
+                tracing::error!("Failed to set up ABDM verification: {}", e);
+                None
+            }
+        });
+        if verifier.is_none() {
+            tracing::warn!("No ABDM credentials configured; demographics cannot be verified");
+        }
+        Self::with_verifier(pool, config, verifier)
+    }
+
+    /// Create the service with the authority demographics are verified with
+    pub fn with_verifier(
+        pool: PgPool,
+        config: IdentityVerificationConfig,
+        verifier: Option<Arc<dyn DemographicVerifier>>,
+    ) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            audit_service: AuditService::new(pool.clone()),
+            pool,
+            verifier,
+            config,
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify a patient's demographics. Their consent is recorded first and
+    /// nothing is sent without it. The attempt is audited before the
+    /// authority is called, and its answer before it is returned; if either
+    /// cannot be audited the verification fails.
+    pub async fn verify(
+        &self,
+        request: VerifyRequest,
+        actor: Uuid,
+        source: RequestSource,
+    ) -> Result<IdentityVerification, HimsError> {
+        self.require(actor, &[Action::Create]).await?;
+        let demographics = &request.demographics;
+        demographics.validate()?;
+        let masked_number = demographics.masked_number()?;
+        let number_hash = demographics.number_hash()?;
+        let cache_key = demographics.cache_key()?;
+        let verification_id = Uuid::new_v4();
+        let audit = |stage: &str, outcome: AuditOutcome, detail: serde_json::Value| {
+            let mut log = AuditLog::new(AuditEventType::Access, AuditAction::Execute, "Patient".to_string())
+                .with_user(actor)
+                .with_resource(verification_id)
+                .with_outcome(outcome)
+                .with_source_info(source.ip_address.clone(), source.user_agent.clone())
+                .with_details(
+                    json!({
+                        "operation": "identity-verification",
+                        "stage": stage,
+                        "document": demographics.document.as_str(),
+                        "number": masked_number,
+                        "detail": detail,
+                    })
+                    .to_string(),
+                );
+            if let Some(patient_id) = request.patient_id {
+                log = log.with_patient(patient_id);
+            }
+            log
+        };
+
+        let consent_id = Uuid::new_v4();
+        sqlx::query(INSERT_CONSENT)
+            .bind(consent_id)
+            .bind(request.patient_id)
+            .bind(demographics.document.as_str())
+            .bind(&number_hash)
+            .bind(request.consent.granted)
+            .bind(request.consent.method.trim())
+            .bind(&request.consent.language)
+            .bind(&request.consent.consenter)
+            .bind(actor)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+        if !request.consent.granted {
+            self.audit(audit("consent-refused", AuditOutcome::MinorFailure, json!({ "consent_id": consent_id })))
+                .await?;
+            return Err(HimsError::ValidationError {
+                message: "The patient has not consented to demographic verification".to_string(),
+            });
+        }
+
+        let cached = sqlx::query(GET_CACHED_VERIFICATION)
+            .bind(&cache_key)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let (result, cached, expires_at) = match cached {
+            Some(row) => {
+                let result = VerificationResult {
+                    outcome: VerificationOutcome::from_string(row.get("outcome"))
+                        .unwrap_or(VerificationOutcome::Mismatch),
+                    reference: row.get("reference"),
+                    detail: row.get("detail"),
+                };
+                (result, true, row.get::<DateTime<Utc>, _>("expires_at"))
+            }
+            None => {
+                let verifier = self.verifier.as_ref().ok_or_else(|| HimsError::ConfigurationError {
+                    message: "Demographic verification is not configured".to_string(),
+                })?;
+                self.audit(audit("request", AuditOutcome::Success, json!({ "consent_id": consent_id })))
+                    .await?;
+                match verifier.verify(demographics).await {
+                    Ok(result) => (result, false, Utc::now() + Duration::hours(self.config.cache_hours)),
+                    Err(e) => {
+                        self.audit(audit("failed", AuditOutcome::SeriousFailure, json!(e.to_string())))
+                            .await?;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        let row = sqlx::query(INSERT_VERIFICATION)
+            .bind(verification_id)
+            .bind(request.patient_id)
+            .bind(demographics.document.as_str())
+            .bind(&number_hash)
+            .bind(&masked_number)
+            .bind(&cache_key)
+            .bind(result.outcome.as_str())
+            .bind(&result.reference)
+            .bind(&result.detail)
+            .bind(cached)
+            .bind(consent_id)
+            .bind(actor)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let outcome = match result.outcome {
+            VerificationOutcome::Verified => AuditOutcome::Success,
+            VerificationOutcome::Mismatch => AuditOutcome::MinorFailure,
+        };
+        self.audit(audit(
+            "result",
+            outcome,
+            json!({ "outcome": result.outcome.as_str(), "cached": cached, "reference": result.reference }),
+        ))
+        .await?;
+        Ok(verification_from_row(&row))
+    }
+
+    /// Verification audit entries are required: one that cannot be written
+    /// stops the verification
+    async fn audit(&self, log: AuditLog) -> Result<(), HimsError> {
+        self.audit_service.create_audit_log(&log).await.map(|_| ()).map_err(|e| {
+            tracing::error!("Failed to audit identity verification: {}", e);
+            HimsError::InternalError {
+                message: "Identity verification could not be audited".to_string(),
+            }
+        })
+    }
+
+    /// Get a verification by ID
+    pub async fn get(&self, id: Uuid, actor: Uuid) -> Result<Option<IdentityVerification>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        let row = sqlx::query(GET_VERIFICATION_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(verification_from_row))
+    }
+
+    /// A patient's verifications, latest first
+    pub async fn list_for_patient(
+        &self,
+        patient_id: Uuid,
+        actor: Uuid,
+    ) -> Result<Vec<IdentityVerification>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        let rows = sqlx::query(LIST_PATIENT_VERIFICATIONS)
+            .bind(patient_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(verification_from_row).collect())
+    }
+}
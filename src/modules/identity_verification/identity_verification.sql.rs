@@ -0,0 +1,46 @@
+//! Identity Verification SQL Queries
+//!
+//! This file contains all SQL queries used by the identity verification service.
+
+/// Record a patient's consent, or refusal, to verification
+pub const INSERT_CONSENT: &str = r#"
+    INSERT INTO identity_verification_consents (
+        id, patient_id, document, number_hash, granted, method, language, consenter, captured_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+"#;
+
+/// The latest unexpired answer for the same demographics
+pub const GET_CACHED_VERIFICATION: &str = r#"
+    SELECT outcome, reference, detail, expires_at
+    FROM identity_verifications
+    WHERE cache_key = $1 AND expires_at > NOW()
+    ORDER BY expires_at DESC
+    LIMIT 1
+"#;
+
+/// Record a verification
+pub const INSERT_VERIFICATION: &str = r#"
+    INSERT INTO identity_verifications (
+        id, patient_id, document, number_hash, masked_number, cache_key, outcome, reference, detail, cached,
+        consent_id, verified_by, expires_at
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+    RETURNING id, patient_id, document, masked_number, outcome, reference, detail, cached, consent_id, verified_by,
+              verified_at, expires_at
+"#;
+
+/// Get a verification by ID
+pub const GET_VERIFICATION_BY_ID: &str = r#"
+    SELECT id, patient_id, document, masked_number, outcome, reference, detail, cached, consent_id, verified_by,
+           verified_at, expires_at
+    FROM identity_verifications
+    WHERE id = $1
+"#;
+
+/// A patient's verifications, latest first
+pub const LIST_PATIENT_VERIFICATIONS: &str = r#"
+    SELECT id, patient_id, document, masked_number, outcome, reference, detail, cached, consent_id, verified_by,
+           verified_at, expires_at
+    FROM identity_verifications
+    WHERE patient_id = $1
+    ORDER BY verified_at DESC
+"#;
@@ -0,0 +1,56 @@
+//! Identity Verification Module
+//!
+//! This module verifies patients' demographics at registration including:
+//! - ABHA demographic authentication through the ABDM sandbox
+//! - Aadhaar demographic authentication through a UIDAI AUA gateway
+//! - The patient's consent, recorded before anything is sent and refused verifications stopped
+//! - Answers reused for the same demographics until they expire
+//! - Every verification audited before and after the authority is called, failing if it cannot be
+//! - Aadhaar numbers kept only masked and hashed
+
+#[path = "identity_verification.controller.rs"]
+pub mod identity_verification_controller;
+#[path = "identity_verification.service.rs"]
+pub mod identity_verification_service;
+#[path = "identity_verification.sql.rs"]
+pub mod identity_verification_sql;
+
+pub use identity_verification_controller::IdentityVerificationController;
+pub use identity_verification_service::{
+    IdentityVerification, IdentityVerificationConfig, IdentityVerificationService, RequestSource,
+    VerificationConsent, VerifyRequest,
+};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Identity Verification Module Configuration
+pub struct IdentityVerificationModule {
+    pub service: Arc<IdentityVerificationService>,
+    pub controller: Arc<IdentityVerificationController>,
+}
+
+impl IdentityVerificationModule {
+    /// Create a new Identity Verification Module with dependency injection
+    pub fn new(db_pool: PgPool, config: IdentityVerificationConfig) -> Self {
+        let service = Arc::new(IdentityVerificationService::new(db_pool, config));
+        let controller = Arc::new(IdentityVerificationController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<IdentityVerificationService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,232 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::immunization::immunization_schedules::ImmunizationSchedule;
+use crate::modules::immunization::immunization_service::{
+    ForecastQuery, Immunization, ImmunizationRequest, PatientForecast, RecallList, RecallQuery,
+};
+use crate::modules::immunization::ImmunizationService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Immunization controller for doses given, forecasts and recall lists
+pub struct ImmunizationController {
+    immunization_service: Arc<ImmunizationService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl ImmunizationController {
+    /// Create new controller with injected service
+    pub fn new(immunization_service: Arc<ImmunizationService>) -> Self {
+        Self { immunization_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::record, "Record an immunization")
+            .get("/recall", Self::recall, "List patients with doses overdue")
+            .get("/schedules", Self::list_schedules, "List immunization schedules")
+            .put("/schedules/:code", Self::save_schedule, "Save a schedule of the tenant's own")
+            .delete("/schedules/:code", Self::delete_schedule, "Remove a schedule of the tenant's own")
+            .get("/patients/:patient_id", Self::list_for_patient, "List a patient's immunizations")
+            .get("/patients/:patient_id/forecast", Self::forecast, "Forecast a patient's doses due")
+            .get("/:id", Self::get, "Get an immunization")
+            .post("/:id/entered-in-error", Self::mark_entered_in_error, "Mark an immunization entered in error")
+            .with_state(self.immunization_service.clone())
+    }
+
+    /// Record an immunization
+    pub async fn record(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Json(payload): Json<ImmunizationRequest>,
+    ) -> Result<(StatusCode, Json<Immunization>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.record(payload, actor).await {
+            Ok(immunization) => Ok((StatusCode::CREATED, Json(immunization))),
+            Err(e) => Err(Self::error_response("Failed to record immunization", e)),
+        }
+    }
+
+    /// Get an immunization
+    pub async fn get(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Immunization>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.get(id, actor).await {
+            Ok(Some(immunization)) => Ok(Json(immunization)),
+            Ok(None) => Err(Self::not_found("Immunization", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve immunization", e)),
+        }
+    }
+
+    /// Mark an immunization entered in error
+    pub async fn mark_entered_in_error(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Immunization>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.mark_entered_in_error(id, actor).await {
+            Ok(Some(immunization)) => Ok(Json(immunization)),
+            Ok(None) => Err(Self::not_found("Immunization", id)),
+            Err(e) => Err(Self::error_response("Failed to mark immunization entered in error", e)),
+        }
+    }
+
+    /// List a patient's immunizations, oldest first
+    pub async fn list_for_patient(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Path(patient_id): Path<Uuid>,
+    ) -> Result<Json<Vec<Immunization>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_for_patient(patient_id, actor).await {
+            Ok(immunizations) => Ok(Json(immunizations)),
+            Err(e) => Err(Self::error_response("Failed to list immunizations", e)),
+        }
+    }
+
+    /// Forecast a patient's doses due, for the chart and portal reminders
+    pub async fn forecast(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Path(patient_id): Path<Uuid>,
+        Query(query): Query<ForecastQuery>,
+    ) -> Result<Json<PatientForecast>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.forecast(patient_id, &query, actor).await {
+            Ok(Some(forecast)) => Ok(Json(forecast)),
+            Ok(None) => Err(Self::not_found("Patient", patient_id)),
+            Err(e) => Err(Self::error_response("Failed to forecast immunizations", e)),
+        }
+    }
+
+    /// List patients with doses overdue, a page at a time
+    pub async fn recall(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Query(query): Query<RecallQuery>,
+    ) -> Result<Json<RecallList>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.recall(&query, actor).await {
+            Ok(recall) => Ok(Json(recall)),
+            Err(e) => Err(Self::error_response("Failed to build recall list", e)),
+        }
+    }
+
+    /// List immunization schedules
+    pub async fn list_schedules(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<ImmunizationSchedule>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_schedules(actor).await {
+            Ok(schedules) => Ok(Json(schedules)),
+            Err(e) => Err(Self::error_response("Failed to list immunization schedules", e)),
+        }
+    }
+
+    /// Save a schedule of the tenant's own
+    pub async fn save_schedule(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Path(code): Path<String>,
+        Json(payload): Json<ImmunizationSchedule>,
+    ) -> Result<Json<ImmunizationSchedule>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.save_schedule(&code, payload, actor).await {
+            Ok(schedule) => Ok(Json(schedule)),
+            Err(e) => Err(Self::error_response("Failed to save immunization schedule", e)),
+        }
+    }
+
+    /// Remove a schedule of the tenant's own
+    pub async fn delete_schedule(
+        State(service): State<Arc<ImmunizationService>>,
+        headers: HeaderMap,
+        Path(code): Path<String>,
+    ) -> Result<StatusCode, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.delete_schedule(&code, actor).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Immunization schedule not found".to_string(),
+                    message: format!("The tenant has no immunization schedule {}", code),
+                }),
+            )),
+            Err(e) => Err(Self::error_response("Failed to remove immunization schedule", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(what: &str, id: Uuid) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", what),
+                message: format!("{} with id {} not found", what, id),
+            }),
+        )
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
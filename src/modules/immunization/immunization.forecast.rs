@@ -0,0 +1,226 @@
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+
+use crate::modules::immunization::immunization_schedules::{ImmunizationSchedule, ScheduledDose, VaccineSeries};
+
+/// Days a dose may fall short of its minimum age or interval and still
+/// count, the grace period CDSi allows
+const GRACE_DAYS: u64 = 4;
+
+/// A dose a patient was given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdministeredDose {
+    /// Vaccine code as `system|code`
+    pub vaccine: String,
+    pub date: NaiveDate,
+}
+
+/// Where a patient stands in a series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoseStatus {
+    /// The next dose is not recommended yet
+    Upcoming,
+    /// The next dose is recommended and not yet past due
+    Due,
+    /// The next dose is past due
+    Overdue,
+    /// Every dose of the series was given
+    Complete,
+    /// The patient is too old to start or go on with the series
+    AgedOut,
+}
+
+/// A patient's progress through one series and the dose they need next
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeriesForecast {
+    pub vaccine: String,
+    pub status: DoseStatus,
+    /// Doses that count toward the series
+    pub doses_given: usize,
+    pub doses_required: usize,
+    /// Number of the dose needed next, counting from one; `None` once the
+    /// series is complete or aged out
+    pub next_dose: Option<usize>,
+    /// When the next dose would first count
+    pub earliest_date: Option<NaiveDate>,
+    pub recommended_date: Option<NaiveDate>,
+    pub past_due_date: Option<NaiveDate>,
+    /// Doses given too young or too soon after the one before, which do
+    /// not count
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub invalid_doses: Vec<NaiveDate>,
+}
+
+impl SeriesForecast {
+    /// Whether the next dose is one to recall the patient for
+    pub fn needs_recall(&self, include_due: bool) -> bool {
+        self.status == DoseStatus::Overdue || (include_due && self.status == DoseStatus::Due)
+    }
+}
+
+/// The first date a dose counts on, ignoring the grace period
+fn earliest_date(dose: &ScheduledDose, birth_date: NaiveDate, previous: Option<NaiveDate>) -> NaiveDate {
+    let by_age = dose.min_age.after(birth_date).unwrap_or(NaiveDate::MAX);
+    let by_interval = previous
+        .zip(dose.min_interval)
+        .map(|(previous, interval)| interval.after(previous).unwrap_or(NaiveDate::MAX))
+        .unwrap_or(NaiveDate::MIN);
+    by_age.max(by_interval)
+}
+
+/// Forecast one series from the doses given before `as_of`. Doses are
+/// matched to the series in date order; one given too young or too soon
+/// does not count, and the dose it was meant as is still needed.
+pub fn forecast_series(
+    series: &VaccineSeries,
+    birth_date: NaiveDate,
+    history: &[AdministeredDose],
+    as_of: NaiveDate,
+) -> SeriesForecast {
+    let mut dates: Vec<NaiveDate> = history
+        .iter()
+        .filter(|dose| dose.date <= as_of && series.codes.contains(&dose.vaccine))
+        .map(|dose| dose.date)
+        .collect();
+    dates.sort();
+
+    let mut given = 0;
+    let mut previous = None;
+    let mut invalid_doses = Vec::new();
+    for date in dates {
+        let Some(dose) = series.doses.get(given) else {
+            break;
+        };
+        let counts_from = earliest_date(dose, birth_date, previous)
+            .checked_sub_days(Days::new(GRACE_DAYS))
+            .unwrap_or(NaiveDate::MIN);
+        if date >= birth_date && date >= counts_from && previous != Some(date) {
+            given += 1;
+            previous = Some(date);
+        } else {
+            invalid_doses.push(date);
+        }
+    }
+
+    let mut forecast = SeriesForecast {
+        vaccine: series.vaccine.clone(),
+        status: DoseStatus::Complete,
+        doses_given: given,
+        doses_required: series.doses.len(),
+        next_dose: None,
+        earliest_date: None,
+        recommended_date: None,
+        past_due_date: None,
+        invalid_doses,
+    };
+    let Some(dose) = series.doses.get(given) else {
+        return forecast;
+    };
+    if series.max_age.and_then(|age| age.after(birth_date)).is_some_and(|limit| as_of >= limit) {
+        forecast.status = DoseStatus::AgedOut;
+        return forecast;
+    }
+
+    let earliest = earliest_date(dose, birth_date, previous);
+    let recommended = dose.recommended_age.after(birth_date).unwrap_or(NaiveDate::MAX).max(earliest);
+    let past_due = dose.past_due_age.after(birth_date).unwrap_or(NaiveDate::MAX).max(recommended);
+    forecast.status = if as_of < recommended {
+        DoseStatus::Upcoming
+    } else if as_of < past_due {
+        DoseStatus::Due
+    } else {
+        DoseStatus::Overdue
+    };
+    forecast.next_dose = Some(given + 1);
+    forecast.earliest_date = Some(earliest);
+    forecast.recommended_date = Some(recommended);
+    forecast.past_due_date = Some(past_due);
+    forecast
+}
+
+/// Forecast every series of a schedule for a patient born on `birth_date`
+pub fn forecast(
+    schedule: &ImmunizationSchedule,
+    birth_date: NaiveDate,
+    history: &[AdministeredDose],
+    as_of: NaiveDate,
+) -> Vec<SeriesForecast> {
+    schedule
+        .series
+        .iter()
+        .map(|series| forecast_series(series, birth_date, history, as_of))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::constants::CVX_SYSTEM;
+    use crate::modules::immunization::immunization_schedules::{acip_schedule, iap_schedule};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn given(cvx: &str, date: NaiveDate) -> AdministeredDose {
+        AdministeredDose { vaccine: format!("{}|{}", CVX_SYSTEM, cvx), date }
+    }
+
+    fn series<'a>(forecasts: &'a [SeriesForecast], vaccine: &str) -> &'a SeriesForecast {
+        forecasts.iter().find(|forecast| forecast.vaccine == vaccine).unwrap()
+    }
+
+    #[test]
+    fn test_forecast_acip() {
+        let birth = date(2024, 1, 1);
+        let forecasts = forecast(&acip_schedule(), birth, &[], date(2024, 3, 15));
+
+        let hepatitis_b = series(&forecasts, "Hepatitis B");
+        assert_eq!(hepatitis_b.status, DoseStatus::Overdue);
+        assert_eq!(hepatitis_b.doses_given, 0);
+        assert_eq!(hepatitis_b.next_dose, Some(1));
+        assert_eq!(hepatitis_b.recommended_date, Some(birth));
+        let dtap = series(&forecasts, "DTaP");
+        assert_eq!(dtap.status, DoseStatus::Due);
+        assert_eq!(dtap.recommended_date, Some(date(2024, 3, 1)));
+        assert_eq!(dtap.past_due_date, Some(date(2024, 4, 1)));
+        assert_eq!(series(&forecasts, "MMR").status, DoseStatus::Upcoming);
+
+        // Rotavirus is not started after 8 months; a combination vaccine
+        // counts toward each of its series
+        let history = [given("146", date(2024, 3, 1)), given("146", date(2024, 5, 1))];
+        let forecasts = forecast(&acip_schedule(), birth, &history, date(2024, 10, 1));
+        assert_eq!(series(&forecasts, "Rotavirus").status, DoseStatus::AgedOut);
+        assert_eq!(series(&forecasts, "Hib").doses_given, 2);
+        assert_eq!(series(&forecasts, "Polio").doses_given, 2);
+        assert!(series(&forecasts, "Hib").needs_recall(false));
+    }
+
+    #[test]
+    fn test_invalid_doses() {
+        let birth = date(2024, 1, 1);
+        let schedule = iap_schedule();
+        let dtap = &schedule.series[3];
+        assert_eq!(dtap.vaccine, "DTP");
+
+        // Six weeks is 12 February, and four days short still counts; the
+        // second dose waits for ten weeks, four after the first
+        let history = [given("20", date(2024, 2, 5)), given("20", date(2024, 2, 8)), given("20", date(2024, 2, 20))];
+        let forecast = forecast_series(dtap, birth, &history, date(2024, 3, 1));
+        assert_eq!(forecast.doses_given, 1);
+        assert_eq!(forecast.invalid_doses, vec![date(2024, 2, 5), date(2024, 2, 20)]);
+        assert_eq!(forecast.earliest_date, Some(date(2024, 3, 11)));
+        assert_eq!(forecast.recommended_date, Some(date(2024, 3, 11)));
+        assert_eq!(forecast.status, DoseStatus::Upcoming);
+
+        let history: Vec<AdministeredDose> = [(2024, 2, 12), (2024, 3, 11), (2024, 4, 8), (2025, 5, 1), (2028, 1, 1)]
+            .into_iter()
+            .map(|(year, month, day)| given("20", date(year, month, day)))
+            .collect();
+        let forecast = forecast_series(dtap, birth, &history, date(2028, 2, 1));
+        assert_eq!(forecast.status, DoseStatus::Complete);
+        assert_eq!(forecast.doses_given, 5);
+        assert!(!forecast.needs_recall(true));
+    }
+}
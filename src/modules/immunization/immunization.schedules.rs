@@ -0,0 +1,461 @@
+use chrono::{Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::models::constants::CVX_SYSTEM;
+
+/// Most series one schedule may hold
+const MAX_SERIES: usize = 50;
+
+/// Most doses one series may hold
+const MAX_DOSES: usize = 10;
+
+/// Most codes that count toward one series
+const MAX_CODES: usize = 50;
+
+/// Schedules are forecast for patients up to this age
+const MAX_AGE_YEARS: u32 = 120;
+
+/// A span of calendar time, written as in `6w`, `15m` or `1y6m`. Months
+/// and years are calendar ones, so two months after 31 December is the
+/// last day of February.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AgeSpan {
+    pub years: u32,
+    pub months: u32,
+    pub weeks: u32,
+    pub days: u32,
+}
+
+impl AgeSpan {
+    pub const fn days(days: u32) -> Self {
+        Self { years: 0, months: 0, weeks: 0, days }
+    }
+
+    pub const fn weeks(weeks: u32) -> Self {
+        Self { years: 0, months: 0, weeks, days: 0 }
+    }
+
+    pub const fn months(months: u32) -> Self {
+        Self { years: 0, months, weeks: 0, days: 0 }
+    }
+
+    pub const fn years(years: u32) -> Self {
+        Self { years, months: 0, weeks: 0, days: 0 }
+    }
+
+    /// The date this long after `date`; `None` past the end of the calendar
+    pub fn after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        date.checked_add_months(Months::new(self.years.checked_mul(12)?.checked_add(self.months)?))?
+            .checked_add_days(Days::new(self.weeks as u64 * 7 + self.days as u64))
+    }
+}
+
+impl fmt::Display for AgeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [(self.years, 'y'), (self.months, 'm'), (self.weeks, 'w'), (self.days, 'd')];
+        let mut written = false;
+        for (amount, unit) in parts.into_iter().filter(|(amount, _)| *amount > 0) {
+            write!(f, "{}{}", amount, unit)?;
+            written = true;
+        }
+        if !written {
+            write!(f, "0d")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AgeSpan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{:?} is not an age such as 6w, 15m or 1y6m", s);
+        let mut span = AgeSpan::default();
+        let mut amount = String::new();
+        let mut parts = 0;
+        for c in s.trim().chars() {
+            if c.is_ascii_digit() {
+                amount.push(c);
+                continue;
+            }
+            let value: u32 = amount.parse().map_err(|_| invalid())?;
+            if value > 9_999 {
+                return Err(invalid());
+            }
+            match c.to_ascii_lowercase() {
+                'y' => span.years += value,
+                'm' => span.months += value,
+                'w' => span.weeks += value,
+                'd' => span.days += value,
+                _ => return Err(invalid()),
+            }
+            amount.clear();
+            parts += 1;
+        }
+        if parts == 0 || !amount.is_empty() {
+            return Err(invalid());
+        }
+        Ok(span)
+    }
+}
+
+impl TryFrom<String> for AgeSpan {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<AgeSpan> for String {
+    fn from(span: AgeSpan) -> Self {
+        span.to_string()
+    }
+}
+
+/// One dose of a series, timed as the CDC's clinical decision support
+/// (CDSi) logic times it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledDose {
+    /// Youngest a patient may be for the dose to count
+    pub min_age: AgeSpan,
+    /// Age the dose is recommended at
+    pub recommended_age: AgeSpan,
+    /// Age past which the dose is overdue
+    pub past_due_age: AgeSpan,
+    /// Shortest time after the series' previous dose for the dose to count;
+    /// every dose but the first has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_interval: Option<AgeSpan>,
+}
+
+/// The doses of one vaccine, and the vaccine codes that count toward them.
+/// Combination vaccines count toward every series listing their code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VaccineSeries {
+    /// Name of the series, e.g. `DTaP`
+    pub vaccine: String,
+    /// Vaccine codes as `system|code`
+    pub codes: Vec<String>,
+    pub doses: Vec<ScheduledDose>,
+    /// Age past which the series is no longer given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<AgeSpan>,
+}
+
+/// A schedule of vaccine series, built in or configured by a tenant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImmunizationSchedule {
+    /// Short code the schedule is chosen by, e.g. `cdc-acip`
+    pub code: String,
+    pub name: String,
+    pub series: Vec<VaccineSeries>,
+}
+
+impl ImmunizationSchedule {
+    /// Check the schedule and put it in its stored form
+    pub fn check(&mut self) -> Result<(), String> {
+        self.code = self.code.trim().to_lowercase();
+        self.name = self.name.trim().to_string();
+        if self.code.is_empty() || !self.code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err("A schedule code is letters, digits and hyphens".to_string());
+        }
+        if self.name.is_empty() {
+            return Err("A schedule needs a name".to_string());
+        }
+        if self.series.is_empty() || self.series.len() > MAX_SERIES {
+            return Err(format!("A schedule has 1 to {} series", MAX_SERIES));
+        }
+
+        // Ages are compared as dates after a birth date at the end of a
+        // month, where calendar months are shortest
+        let birth = NaiveDate::from_ymd_opt(2000, 1, 31).expect("valid date");
+        let oldest = AgeSpan::years(MAX_AGE_YEARS).after(birth);
+        let date = |span: &AgeSpan| span.after(birth).filter(|date| Some(*date) <= oldest);
+        let mut vaccines: Vec<String> = Vec::new();
+        for series in &mut self.series {
+            series.vaccine = series.vaccine.trim().to_string();
+            if series.vaccine.is_empty() {
+                return Err("Every series needs a vaccine name".to_string());
+            }
+            if vaccines.contains(&series.vaccine.to_lowercase()) {
+                return Err(format!("The schedule has two {} series", series.vaccine));
+            }
+            vaccines.push(series.vaccine.to_lowercase());
+            let invalid = |message: &str| format!("{}: {}", series.vaccine, message);
+
+            if series.codes.is_empty() || series.codes.len() > MAX_CODES {
+                return Err(invalid(&format!("a series has 1 to {} vaccine codes", MAX_CODES)));
+            }
+            for code in &series.codes {
+                if !code.split_once('|').is_some_and(|(system, code)| !system.is_empty() && !code.is_empty()) {
+                    return Err(invalid(&format!("{:?} is not a vaccine code as system|code", code)));
+                }
+            }
+            if series.doses.is_empty() || series.doses.len() > MAX_DOSES {
+                return Err(invalid(&format!("a series has 1 to {} doses", MAX_DOSES)));
+            }
+            for (i, dose) in series.doses.iter().enumerate() {
+                let ages = [dose.min_age, dose.recommended_age, dose.past_due_age].map(|age| date(&age));
+                if ages.iter().any(Option::is_none) {
+                    return Err(invalid(&format!("ages are at most {} years", MAX_AGE_YEARS)));
+                }
+                if ages[0] > ages[1] || ages[1] > ages[2] {
+                    return Err(invalid(&format!(
+                        "dose {} is recommended between its minimum and past due ages",
+                        i + 1
+                    )));
+                }
+                match dose.min_interval {
+                    None if i > 0 => return Err(invalid(&format!("dose {} needs a minimum interval", i + 1))),
+                    Some(interval) if date(&interval).is_none() => {
+                        return Err(invalid(&format!("intervals are at most {} years", MAX_AGE_YEARS)))
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(max_age) = series.max_age {
+                if date(&max_age).is_none() || date(&max_age) <= date(&series.doses[0].min_age) {
+                    return Err(invalid("the maximum age is after the first dose's minimum age"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn d(days: u32) -> AgeSpan {
+    AgeSpan::days(days)
+}
+
+const fn w(weeks: u32) -> AgeSpan {
+    AgeSpan::weeks(weeks)
+}
+
+const fn m(months: u32) -> AgeSpan {
+    AgeSpan::months(months)
+}
+
+const fn y(years: u32) -> AgeSpan {
+    AgeSpan::years(years)
+}
+
+fn dose(
+    min_age: AgeSpan,
+    recommended_age: AgeSpan,
+    past_due_age: AgeSpan,
+    min_interval: Option<AgeSpan>,
+) -> ScheduledDose {
+    ScheduledDose { min_age, recommended_age, past_due_age, min_interval }
+}
+
+fn series(vaccine: &str, cvx: &[&str], max_age: Option<AgeSpan>, doses: Vec<ScheduledDose>) -> VaccineSeries {
+    VaccineSeries {
+        vaccine: vaccine.to_string(),
+        codes: cvx.iter().map(|code| format!("{}|{}", CVX_SYSTEM, code)).collect(),
+        doses,
+        max_age,
+    }
+}
+
+// CVX codes counting toward each series, combination vaccines included
+const HEPATITIS_B: &[&str] = &["08", "43", "44", "45", "51", "102", "104", "110", "146", "198"];
+const ROTAVIRUS: &[&str] = &["116", "119", "122"];
+const DIPHTHERIA_TETANUS_PERTUSSIS: &[&str] =
+    &["01", "20", "50", "102", "106", "107", "110", "120", "130", "146", "198"];
+const HIB: &[&str] = &["17", "46", "47", "48", "49", "50", "51", "102", "120", "146", "148", "198"];
+const PNEUMOCOCCAL_CONJUGATE: &[&str] = &["100", "133", "152", "177", "215", "216"];
+const INACTIVATED_POLIO: &[&str] = &["10", "89", "110", "120", "130", "146"];
+const ORAL_POLIO: &[&str] = &["02", "178", "179", "182"];
+const MEASLES: &[&str] = &["03", "04", "05", "94"];
+const VARICELLA: &[&str] = &["21", "94"];
+const HEPATITIS_A: &[&str] = &["31", "52", "83", "85", "104"];
+const TETANUS_DIPHTHERIA_ADOLESCENT: &[&str] = &["09", "113", "115", "138", "139"];
+const HPV: &[&str] = &["62", "118", "137", "165"];
+const MENINGOCOCCAL_ACWY: &[&str] = &["108", "114", "136", "147", "203"];
+const TYPHOID_CONJUGATE: &[&str] = &["190"];
+const BCG: &[&str] = &["19"];
+
+/// The CDC ACIP child and adolescent schedule, birth to 18 years. Seasonal
+/// vaccines and those for risk groups are left out.
+pub fn acip_schedule() -> ImmunizationSchedule {
+    ImmunizationSchedule {
+        code: "cdc-acip".to_string(),
+        name: "CDC ACIP child and adolescent schedule".to_string(),
+        series: vec![
+            series("Hepatitis B", HEPATITIS_B, None, vec![
+                dose(d(0), d(0), m(2), None),
+                dose(w(4), m(1), m(3), Some(w(4))),
+                dose(w(24), m(6), m(19), Some(w(8))),
+            ]),
+            series("Rotavirus", ROTAVIRUS, Some(m(8)), vec![
+                dose(w(6), m(2), m(3), None),
+                dose(w(10), m(4), m(5), Some(w(4))),
+                dose(w(14), m(6), m(7), Some(w(4))),
+            ]),
+            series("DTaP", DIPHTHERIA_TETANUS_PERTUSSIS, Some(y(7)), vec![
+                dose(w(6), m(2), m(3), None),
+                dose(w(10), m(4), m(5), Some(w(4))),
+                dose(w(14), m(6), m(7), Some(w(4))),
+                dose(m(12), m(15), m(19), Some(m(6))),
+                dose(y(4), y(4), y(7), Some(m(6))),
+            ]),
+            series("Hib", HIB, Some(y(5)), vec![
+                dose(w(6), m(2), m(3), None),
+                dose(w(10), m(4), m(5), Some(w(4))),
+                dose(w(14), m(6), m(7), Some(w(4))),
+                dose(m(12), m(12), m(16), Some(w(8))),
+            ]),
+            series("Pneumococcal conjugate", PNEUMOCOCCAL_CONJUGATE, Some(y(5)), vec![
+                dose(w(6), m(2), m(3), None),
+                dose(w(10), m(4), m(5), Some(w(4))),
+                dose(w(14), m(6), m(7), Some(w(4))),
+                dose(m(12), m(12), m(16), Some(w(8))),
+            ]),
+            series("Polio", INACTIVATED_POLIO, Some(y(18)), vec![
+                dose(w(6), m(2), m(3), None),
+                dose(w(10), m(4), m(5), Some(w(4))),
+                dose(w(14), m(6), m(19), Some(w(4))),
+                dose(y(4), y(4), y(7), Some(m(6))),
+            ]),
+            series("MMR", MEASLES, None, vec![
+                dose(m(12), m(12), m(16), None),
+                dose(m(13), y(4), y(7), Some(w(4))),
+            ]),
+            series("Varicella", VARICELLA, None, vec![
+                dose(m(12), m(12), m(16), None),
+                dose(m(15), y(4), y(7), Some(m(3))),
+            ]),
+            series("Hepatitis A", HEPATITIS_A, None, vec![
+                dose(m(12), m(12), m(24), None),
+                dose(m(18), m(18), y(3), Some(m(6))),
+            ]),
+            series("Tdap", TETANUS_DIPHTHERIA_ADOLESCENT, None, vec![dose(y(7), y(11), y(13), None)]),
+            series("HPV", HPV, Some(y(27)), vec![
+                dose(y(9), y(11), y(13), None),
+                dose(y(9), y(11), y(13), Some(m(5))),
+            ]),
+            series("MenACWY", MENINGOCOCCAL_ACWY, Some(y(22)), vec![
+                dose(y(10), y(11), y(13), None),
+                dose(y(16), y(16), y(17), Some(w(8))),
+            ]),
+        ],
+    }
+}
+
+/// The Indian Academy of Pediatrics (IAP ACVIP) schedule, birth to 18
+/// years. Seasonal vaccines and those for endemic areas, such as Japanese
+/// encephalitis, are left to tenant schedules.
+pub fn iap_schedule() -> ImmunizationSchedule {
+    ImmunizationSchedule {
+        code: "iap".to_string(),
+        name: "IAP immunization schedule".to_string(),
+        series: vec![
+            series("BCG", BCG, Some(y(5)), vec![dose(d(0), d(0), m(1), None)]),
+            series("OPV birth dose", ORAL_POLIO, Some(d(15)), vec![dose(d(0), d(0), d(7), None)]),
+            series("Hepatitis B", HEPATITIS_B, None, vec![
+                dose(d(0), d(0), m(1), None),
+                dose(w(6), w(6), w(10), Some(w(4))),
+                dose(w(10), w(10), w(14), Some(w(4))),
+                dose(w(14), w(14), w(18), Some(w(4))),
+            ]),
+            series("DTP", DIPHTHERIA_TETANUS_PERTUSSIS, Some(y(7)), vec![
+                dose(w(6), w(6), w(10), None),
+                dose(w(10), w(10), w(14), Some(w(4))),
+                dose(w(14), w(14), w(18), Some(w(4))),
+                dose(m(12), m(16), m(19), Some(m(6))),
+                dose(y(4), y(4), y(7), Some(m(6))),
+            ]),
+            series("Hib", HIB, Some(y(5)), vec![
+                dose(w(6), w(6), w(10), None),
+                dose(w(10), w(10), w(14), Some(w(4))),
+                dose(w(14), w(14), w(18), Some(w(4))),
+                dose(m(12), m(16), m(19), Some(m(6))),
+            ]),
+            series("IPV", INACTIVATED_POLIO, Some(y(18)), vec![
+                dose(w(6), w(6), w(10), None),
+                dose(w(10), w(10), w(14), Some(w(4))),
+                dose(w(14), w(14), w(18), Some(w(4))),
+                dose(m(12), m(16), m(19), Some(m(6))),
+            ]),
+            series("Rotavirus", ROTAVIRUS, Some(m(8)), vec![
+                dose(w(6), w(6), w(10), None),
+                dose(w(10), w(10), w(14), Some(w(4))),
+                dose(w(14), w(14), w(18), Some(w(4))),
+            ]),
+            series("Pneumococcal conjugate", PNEUMOCOCCAL_CONJUGATE, Some(y(5)), vec![
+                dose(w(6), w(6), w(10), None),
+                dose(w(10), w(10), w(14), Some(w(4))),
+                dose(w(14), w(14), w(18), Some(w(4))),
+                dose(m(12), m(12), m(16), Some(w(8))),
+            ]),
+            series("Typhoid conjugate", TYPHOID_CONJUGATE, None, vec![dose(m(6), m(9), m(13), None)]),
+            series("MMR", MEASLES, None, vec![
+                dose(m(9), m(9), m(12), None),
+                dose(m(12), m(15), m(19), Some(w(4))),
+                dose(y(4), y(4), y(7), Some(w(4))),
+            ]),
+            series("Hepatitis A", HEPATITIS_A, None, vec![
+                dose(m(12), m(12), m(16), None),
+                dose(m(18), m(18), y(3), Some(m(6))),
+            ]),
+            series("Varicella", VARICELLA, None, vec![
+                dose(m(12), m(15), m(19), None),
+                dose(m(15), m(18), y(2), Some(m(3))),
+            ]),
+            series("Tdap/Td", TETANUS_DIPHTHERIA_ADOLESCENT, None, vec![dose(y(7), y(10), y(13), None)]),
+            series("HPV", HPV, Some(y(27)), vec![
+                dose(y(9), y(9), y(15), None),
+                dose(y(9), y(9), y(15), Some(m(6))),
+            ]),
+        ],
+    }
+}
+
+/// Schedules offered without configuration. A tenant's schedule of the
+/// same code takes the place of one.
+pub fn builtin_schedules() -> Vec<ImmunizationSchedule> {
+    vec![acip_schedule(), iap_schedule()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_span() {
+        let span: AgeSpan = "1y6m".parse().unwrap();
+        assert_eq!(span, AgeSpan { years: 1, months: 6, weeks: 0, days: 0 });
+        assert_eq!(span.to_string(), "1y6m");
+        assert_eq!(AgeSpan::default().to_string(), "0d");
+        assert!("".parse::<AgeSpan>().is_err());
+        assert!("w".parse::<AgeSpan>().is_err());
+        assert!("6x".parse::<AgeSpan>().is_err());
+        assert!("6".parse::<AgeSpan>().is_err());
+
+        let birth = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+        assert_eq!(m(2).after(birth), NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert_eq!(w(6).after(birth), NaiveDate::from_ymd_opt(2024, 2, 11));
+    }
+
+    #[test]
+    fn test_check_schedules() {
+        for mut schedule in builtin_schedules() {
+            schedule.check().unwrap();
+        }
+
+        let mut schedule = acip_schedule();
+        schedule.series[0].doses[1].min_interval = None;
+        assert!(schedule.check().is_err(), "no interval after the first dose");
+        let mut schedule = acip_schedule();
+        schedule.series[0].doses[0].past_due_age = w(0);
+        schedule.series[0].doses[0].recommended_age = w(2);
+        assert!(schedule.check().is_err(), "past due before recommended");
+        let mut schedule = acip_schedule();
+        schedule.series.push(schedule.series[0].clone());
+        assert!(schedule.check().is_err(), "series twice");
+    }
+}
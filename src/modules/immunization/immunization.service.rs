@@ -0,0 +1,548 @@
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::CVX_SYSTEM;
+use crate::modules::authorization::Action;
+use crate::modules::immunization::immunization_forecast::{forecast, AdministeredDose, SeriesForecast};
+use crate::modules::immunization::immunization_schedules::{builtin_schedules, ImmunizationSchedule};
+use crate::modules::role::RoleService;
+
+// Import SQL queries
+use crate::modules::immunization::immunization_sql::*;
+
+/// Patients looked at per query while building a recall list
+const RECALL_BATCH: i64 = 500;
+
+/// Largest recall list page
+const MAX_RECALL_PAGE: u32 = 500;
+
+/// Oldest patients a recall list looks at unless asked otherwise
+const DEFAULT_RECALL_MAX_AGE_YEARS: u32 = 18;
+
+/// Oldest patients a recall list may look at
+const MAX_RECALL_AGE_YEARS: u32 = 120;
+
+/// Immunization settings
+#[derive(Debug, Clone)]
+pub struct ImmunizationConfig {
+    /// Schedule forecasts use unless asked for another
+    pub default_schedule: String,
+}
+
+impl Default for ImmunizationConfig {
+    fn default() -> Self {
+        Self {
+            default_schedule: "cdc-acip".to_string(),
+        }
+    }
+}
+
+impl ImmunizationConfig {
+    /// Settings from `IMMUNIZATION_SCHEDULE`, falling back to the IAP
+    /// schedule for deployments whose `HIMS_DATA_COUNTRY` is India
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let default_schedule = var("IMMUNIZATION_SCHEDULE").unwrap_or_else(|| {
+            match var("HIMS_DATA_COUNTRY").as_deref().map(str::to_ascii_uppercase).as_deref() {
+                Some("IN") => "iap".to_string(),
+                _ => Self::default().default_schedule,
+            }
+        });
+        Self {
+            default_schedule: default_schedule.trim().to_lowercase(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImmunizationStatus {
+    Completed,
+    NotDone,
+    EnteredInError,
+}
+
+impl ImmunizationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImmunizationStatus::Completed => "completed",
+            ImmunizationStatus::NotDone => "not-done",
+            ImmunizationStatus::EnteredInError => "entered-in-error",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "completed" => Some(ImmunizationStatus::Completed),
+            "not-done" => Some(ImmunizationStatus::NotDone),
+            "entered-in-error" => Some(ImmunizationStatus::EnteredInError),
+            _ => None,
+        }
+    }
+}
+
+/// A dose given, or recorded as not given
+#[derive(Debug, Clone, Serialize)]
+pub struct Immunization {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub vaccine_system: String,
+    pub vaccine_code: String,
+    pub vaccine_display: Option<String>,
+    pub occurrence_date: NaiveDate,
+    pub status: ImmunizationStatus,
+    pub lot_number: Option<String>,
+    pub note: Option<String>,
+    pub recorded_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An immunization to record
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImmunizationRequest {
+    pub patient_id: Uuid,
+    /// Code system of the vaccine; CVX if not given
+    pub vaccine_system: Option<String>,
+    pub vaccine_code: String,
+    pub vaccine_display: Option<String>,
+    pub occurrence_date: NaiveDate,
+    /// Completed if not given; a dose not given is recorded as not-done
+    pub status: Option<ImmunizationStatus>,
+    pub lot_number: Option<String>,
+    pub note: Option<String>,
+}
+
+impl ImmunizationRequest {
+    /// Check the immunization and put it in its stored form
+    pub fn check(&mut self) -> Result<(), String> {
+        let system = self.vaccine_system.take().unwrap_or_else(|| CVX_SYSTEM.to_string());
+        self.vaccine_system = Some(system.trim().to_string()).filter(|system| !system.is_empty());
+        self.vaccine_code = self.vaccine_code.trim().to_string();
+        if self.vaccine_system.is_none() || self.vaccine_code.is_empty() || self.vaccine_code.contains('|') {
+            return Err("An immunization needs a vaccine code".to_string());
+        }
+        if self.occurrence_date > Utc::now().date_naive() {
+            return Err("Immunizations cannot be given in the future".to_string());
+        }
+        if self.status == Some(ImmunizationStatus::EnteredInError) {
+            return Err("Immunizations are marked entered in error once recorded".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Which schedule to forecast against, and as of when
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ForecastQuery {
+    /// Schedule code; the configured default if not given
+    pub schedule: Option<String>,
+    /// Today if not given
+    pub as_of: Option<NaiveDate>,
+}
+
+/// A patient's doses due against a schedule
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientForecast {
+    pub patient_id: Uuid,
+    pub birth_date: NaiveDate,
+    pub schedule: String,
+    pub as_of: NaiveDate,
+    pub series: Vec<SeriesForecast>,
+}
+
+/// A page of patients to recall
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RecallQuery {
+    /// Schedule code; the configured default if not given
+    pub schedule: Option<String>,
+    /// Recall patients whose doses are due as well as overdue
+    #[serde(default)]
+    pub include_due: bool,
+    /// Oldest patients looked at, in years
+    pub max_age_years: Option<u32>,
+    /// Patient the previous page ended with
+    pub after: Option<Uuid>,
+    pub limit: Option<u32>,
+}
+
+/// A patient to recall, with how to reach them and the doses they need
+#[derive(Debug, Clone, Serialize)]
+pub struct RecallEntry {
+    pub patient_id: Uuid,
+    /// FHIR HumanNames
+    pub name: Value,
+    /// FHIR ContactPoints
+    pub telecom: Option<Value>,
+    pub birth_date: NaiveDate,
+    pub doses: Vec<SeriesForecast>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecallList {
+    pub schedule: String,
+    pub as_of: NaiveDate,
+    pub patients: Vec<RecallEntry>,
+    /// Patient to continue after for the next page; `None` on the last
+    pub next_after: Option<Uuid>,
+}
+
+fn immunization_from_row(row: &PgRow) -> Immunization {
+    Immunization {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        vaccine_system: row.get("vaccine_system"),
+        vaccine_code: row.get("vaccine_code"),
+        vaccine_display: row.get("vaccine_display"),
+        occurrence_date: row.get("occurrence_date"),
+        status: ImmunizationStatus::from_string(&row.get::<String, _>("status"))
+            .unwrap_or(ImmunizationStatus::Completed),
+        lot_number: row.get("lot_number"),
+        note: row.get("note"),
+        recorded_by: row.get("recorded_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn schedule_from_row(row: &PgRow) -> Result<ImmunizationSchedule, HimsError> {
+    let series = serde_json::from_value(row.get("series")).map_err(|e| HimsError::InternalError {
+        message: format!("Stored immunization schedule is not valid: {}", e),
+    })?;
+    Ok(ImmunizationSchedule {
+        code: row.get("code"),
+        name: row.get("name"),
+        series,
+    })
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+/// Immunization service: doses given, and forecasts of the doses due
+/// against configurable schedules for patients and recall lists
+pub struct ImmunizationService {
+    pool: PgPool,
+    role_service: RoleService,
+    config: ImmunizationConfig,
+}
+
+impl ImmunizationService {
+    /// Create new immunization service
+    pub fn new(pool: PgPool, config: ImmunizationConfig) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+            config,
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Record an immunization
+    pub async fn record(&self, mut request: ImmunizationRequest, actor: Uuid) -> Result<Immunization, HimsError> {
+        request.check().map_err(validation_error)?;
+        self.require(actor, &[Action::Create]).await?;
+
+        let birth_date = self
+            .birth_date(request.patient_id)
+            .await?
+            .ok_or_else(|| validation_error(format!("Patient {} does not exist", request.patient_id)))?;
+        if birth_date.is_some_and(|birth_date| request.occurrence_date < birth_date) {
+            return Err(validation_error("Immunizations cannot be given before birth".to_string()));
+        }
+
+        let row = sqlx::query(INSERT_IMMUNIZATION)
+            .bind(Uuid::new_v4())
+            .bind(request.patient_id)
+            .bind(&request.vaccine_system)
+            .bind(&request.vaccine_code)
+            .bind(&request.vaccine_display)
+            .bind(request.occurrence_date)
+            .bind(request.status.unwrap_or(ImmunizationStatus::Completed).as_str())
+            .bind(&request.lot_number)
+            .bind(&request.note)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let immunization = immunization_from_row(&row);
+
+        tracing::info!(
+            "Immunization {} of patient {} recorded by {}",
+            immunization.id,
+            immunization.patient_id,
+            actor
+        );
+        Ok(immunization)
+    }
+
+    /// A patient's birth date: `None` if there is no such patient, and
+    /// `Some(None)` if it is not known
+    async fn birth_date(&self, patient_id: Uuid) -> Result<Option<Option<NaiveDate>>, HimsError> {
+        let row = sqlx::query(GET_PATIENT_BIRTH_DATE)
+            .bind(patient_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.map(|row| row.get("birth_date")))
+    }
+
+    /// Get an immunization
+    pub async fn get(&self, id: Uuid, actor: Uuid) -> Result<Option<Immunization>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        let row = sqlx::query(GET_IMMUNIZATION_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(immunization_from_row))
+    }
+
+    /// Mark an immunization entered in error, so it no longer counts.
+    /// `None` if there is no such immunization or it already is.
+    pub async fn mark_entered_in_error(&self, id: Uuid, actor: Uuid) -> Result<Option<Immunization>, HimsError> {
+        self.require(actor, &[Action::Update]).await?;
+        let row = sqlx::query(MARK_IMMUNIZATION_ENTERED_IN_ERROR)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        if row.is_some() {
+            tracing::info!("Immunization {} marked entered in error by {}", id, actor);
+        }
+        Ok(row.as_ref().map(immunization_from_row))
+    }
+
+    /// A patient's immunizations, oldest first
+    pub async fn list_for_patient(&self, patient_id: Uuid, actor: Uuid) -> Result<Vec<Immunization>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        self.patient_immunizations(patient_id).await
+    }
+
+    async fn patient_immunizations(&self, patient_id: Uuid) -> Result<Vec<Immunization>, HimsError> {
+        let rows = sqlx::query(LIST_PATIENT_IMMUNIZATIONS)
+            .bind(patient_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(immunization_from_row).collect())
+    }
+
+    /// Forecast a patient's doses. `None` if there is no such patient.
+    pub async fn forecast(
+        &self,
+        patient_id: Uuid,
+        query: &ForecastQuery,
+        actor: Uuid,
+    ) -> Result<Option<PatientForecast>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        let schedule = self.resolve_schedule(query.schedule.as_deref()).await?;
+        let Some(birth_date) = self.birth_date(patient_id).await? else {
+            return Ok(None);
+        };
+        let birth_date = birth_date
+            .ok_or_else(|| validation_error(format!("Patient {} has no birth date to forecast from", patient_id)))?;
+
+        let history: Vec<AdministeredDose> = self
+            .patient_immunizations(patient_id)
+            .await?
+            .into_iter()
+            .filter(|immunization| immunization.status == ImmunizationStatus::Completed)
+            .map(|immunization| AdministeredDose {
+                vaccine: format!("{}|{}", immunization.vaccine_system, immunization.vaccine_code),
+                date: immunization.occurrence_date,
+            })
+            .collect();
+        let as_of = query.as_of.unwrap_or_else(|| Utc::now().date_naive());
+
+        Ok(Some(PatientForecast {
+            patient_id,
+            birth_date,
+            series: forecast(&schedule, birth_date, &history, as_of),
+            schedule: schedule.code,
+            as_of,
+        }))
+    }
+
+    /// Patients with doses overdue, or due as well if asked, a page at a
+    /// time in patient order
+    pub async fn recall(&self, query: &RecallQuery, actor: Uuid) -> Result<RecallList, HimsError> {
+        self.require(actor, &[Action::Search]).await?;
+        let schedule = self.resolve_schedule(query.schedule.as_deref()).await?;
+        let limit = query.limit.unwrap_or(100).clamp(1, MAX_RECALL_PAGE) as usize;
+        let max_age_years = query.max_age_years.unwrap_or(DEFAULT_RECALL_MAX_AGE_YEARS);
+        let as_of = Utc::now().date_naive();
+        if max_age_years > MAX_RECALL_AGE_YEARS {
+            return Err(validation_error(format!(
+                "Recall lists look at patients up to {} years old",
+                MAX_RECALL_AGE_YEARS
+            )));
+        }
+        let born_after = as_of
+            .checked_sub_months(Months::new((max_age_years + 1) * 12))
+            .unwrap_or(NaiveDate::MIN);
+
+        let mut patients = Vec::new();
+        let mut after = query.after;
+        loop {
+            let rows = sqlx::query(RECALL_CANDIDATES)
+                .bind(born_after)
+                .bind(after)
+                .bind(RECALL_BATCH)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(database_error)?;
+            let exhausted = (rows.len() as i64) < RECALL_BATCH;
+
+            for row in &rows {
+                let patient_id: Uuid = row.get("id");
+                after = Some(patient_id);
+                let birth_date: NaiveDate = row.get("birth_date");
+                let vaccines: Vec<String> = row.get("vaccines");
+                let dates: Vec<NaiveDate> = row.get("dates");
+                let history: Vec<AdministeredDose> = vaccines
+                    .into_iter()
+                    .zip(dates)
+                    .map(|(vaccine, date)| AdministeredDose { vaccine, date })
+                    .collect();
+
+                let doses: Vec<SeriesForecast> = forecast(&schedule, birth_date, &history, as_of)
+                    .into_iter()
+                    .filter(|forecast| forecast.needs_recall(query.include_due))
+                    .collect();
+                if doses.is_empty() {
+                    continue;
+                }
+                patients.push(RecallEntry {
+                    patient_id,
+                    name: row.get("name"),
+                    telecom: row.get("telecom"),
+                    birth_date,
+                    doses,
+                });
+                if patients.len() == limit {
+                    tracing::info!("User {} built a recall list of {} patients", actor, patients.len());
+                    return Ok(RecallList {
+                        schedule: schedule.code,
+                        as_of,
+                        patients,
+                        next_after: Some(patient_id),
+                    });
+                }
+            }
+            if exhausted {
+                break;
+            }
+        }
+
+        tracing::info!("User {} built a recall list of {} patients", actor, patients.len());
+        Ok(RecallList {
+            schedule: schedule.code,
+            as_of,
+            patients,
+            next_after: None,
+        })
+    }
+
+    /// The schedule of a code, the tenant's own before a built-in one
+    async fn resolve_schedule(&self, code: Option<&str>) -> Result<ImmunizationSchedule, HimsError> {
+        let code = code.map(|code| code.trim().to_lowercase()).unwrap_or_else(|| self.config.default_schedule.clone());
+        let row = sqlx::query(GET_SCHEDULE)
+            .bind(&code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        if let Some(row) = row {
+            return schedule_from_row(&row);
+        }
+        builtin_schedules()
+            .into_iter()
+            .find(|schedule| schedule.code == code)
+            .ok_or_else(|| validation_error(format!("Unknown immunization schedule {}", code)))
+    }
+
+    /// Every schedule, the tenant's own in place of built-in ones of the
+    /// same code
+    pub async fn list_schedules(&self, actor: Uuid) -> Result<Vec<ImmunizationSchedule>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        let rows = sqlx::query(LIST_SCHEDULES)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let mut schedules = rows.iter().map(schedule_from_row).collect::<Result<Vec<_>, _>>()?;
+        for builtin in builtin_schedules() {
+            if !schedules.iter().any(|schedule| schedule.code == builtin.code) {
+                schedules.push(builtin);
+            }
+        }
+        schedules.sort_by(|a, b| a.code.cmp(&b.code));
+        Ok(schedules)
+    }
+
+    /// Save a schedule of the tenant's own
+    pub async fn save_schedule(
+        &self,
+        code: &str,
+        mut schedule: ImmunizationSchedule,
+        actor: Uuid,
+    ) -> Result<ImmunizationSchedule, HimsError> {
+        schedule.code = code.to_string();
+        schedule.check().map_err(validation_error)?;
+        self.require(actor, &[Action::Configure]).await?;
+
+        let series = serde_json::to_value(&schedule.series).map_err(|e| HimsError::InternalError {
+            message: format!("Failed to serialize immunization schedule: {}", e),
+        })?;
+        let row = sqlx::query(UPSERT_SCHEDULE)
+            .bind(&schedule.code)
+            .bind(&schedule.name)
+            .bind(series)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        tracing::info!("Immunization schedule {} saved by {}", schedule.code, actor);
+        schedule_from_row(&row)
+    }
+
+    /// Remove a schedule of the tenant's own, restoring the built-in one of
+    /// its code if there is one
+    pub async fn delete_schedule(&self, code: &str, actor: Uuid) -> Result<bool, HimsError> {
+        self.require(actor, &[Action::Configure]).await?;
+        let rows_affected = sqlx::query(DELETE_SCHEDULE)
+            .bind(code.trim().to_lowercase())
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?
+            .rows_affected();
+
+        if rows_affected > 0 {
+            tracing::info!("Immunization schedule {} removed by {}", code, actor);
+        }
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,97 @@
+//! Immunization SQL Queries
+//!
+//! This file contains all SQL queries used by the immunization service.
+
+/// Record an immunization
+pub const INSERT_IMMUNIZATION: &str = r#"
+    INSERT INTO immunizations (
+        id, patient_id, vaccine_system, vaccine_code, vaccine_display, occurrence_date, status, lot_number, note,
+        recorded_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    RETURNING id, patient_id, vaccine_system, vaccine_code, vaccine_display, occurrence_date, status, lot_number,
+              note, recorded_by, created_at, updated_at
+"#;
+
+/// Get an immunization by ID
+pub const GET_IMMUNIZATION_BY_ID: &str = r#"
+    SELECT id, patient_id, vaccine_system, vaccine_code, vaccine_display, occurrence_date, status, lot_number,
+           note, recorded_by, created_at, updated_at
+    FROM immunizations
+    WHERE id = $1
+"#;
+
+/// A patient's immunizations, oldest first
+pub const LIST_PATIENT_IMMUNIZATIONS: &str = r#"
+    SELECT id, patient_id, vaccine_system, vaccine_code, vaccine_display, occurrence_date, status, lot_number,
+           note, recorded_by, created_at, updated_at
+    FROM immunizations
+    WHERE patient_id = $1
+    ORDER BY occurrence_date, created_at
+"#;
+
+/// Mark an immunization entered in error, unless it already is
+pub const MARK_IMMUNIZATION_ENTERED_IN_ERROR: &str = r#"
+    UPDATE immunizations
+    SET status = 'entered-in-error'
+    WHERE id = $1 AND status <> 'entered-in-error'
+    RETURNING id, patient_id, vaccine_system, vaccine_code, vaccine_display, occurrence_date, status, lot_number,
+              note, recorded_by, created_at, updated_at
+"#;
+
+/// A patient's birth date; no row if there is no such patient
+pub const GET_PATIENT_BIRTH_DATE: &str = r#"
+    SELECT birth_date
+    FROM patients
+    WHERE id = $1
+"#;
+
+/// The tenant's schedules
+pub const LIST_SCHEDULES: &str = r#"
+    SELECT code, name, series
+    FROM immunization_schedules
+    ORDER BY code
+"#;
+
+/// The tenant's schedule of a code
+pub const GET_SCHEDULE: &str = r#"
+    SELECT code, name, series
+    FROM immunization_schedules
+    WHERE code = $1
+"#;
+
+/// Save the tenant's schedule of a code
+pub const UPSERT_SCHEDULE: &str = r#"
+    INSERT INTO immunization_schedules (code, name, series, updated_by)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT ON CONSTRAINT unique_immunization_schedule_code
+    DO UPDATE SET name = EXCLUDED.name, series = EXCLUDED.series, updated_by = EXCLUDED.updated_by
+    RETURNING code, name, series
+"#;
+
+/// Remove the tenant's schedule of a code
+pub const DELETE_SCHEDULE: &str = r#"
+    DELETE FROM immunization_schedules
+    WHERE code = $1
+"#;
+
+/// Living patients born after $1 with the doses they were given, a page at
+/// a time after patient $2
+pub const RECALL_CANDIDATES: &str = r#"
+    SELECT p.id, p.name, p.telecom, p.birth_date,
+           COALESCE(
+               array_agg(i.vaccine_system || '|' || i.vaccine_code ORDER BY i.occurrence_date)
+                   FILTER (WHERE i.id IS NOT NULL),
+               '{}'
+           ) AS vaccines,
+           COALESCE(array_agg(i.occurrence_date ORDER BY i.occurrence_date) FILTER (WHERE i.id IS NOT NULL), '{}')
+               AS dates
+    FROM patients p
+    LEFT JOIN immunizations i ON i.patient_id = p.id AND i.status = 'completed'
+    WHERE p.active = true
+      AND COALESCE(p.deceased, false) = false
+      AND p.birth_date > $1
+      AND ($2::uuid IS NULL OR p.id > $2)
+    GROUP BY p.id
+    ORDER BY p.id
+    LIMIT $3
+"#;
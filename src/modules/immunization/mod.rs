@@ -0,0 +1,57 @@
+//! Immunization Module
+//!
+//! This module tracks immunizations including:
+//! - Doses given, coded in CVX, and corrected by marking them entered in error
+//! - The CDC ACIP and IAP childhood schedules, which tenants may replace with their own
+//! - Forecasts of each series' next dose as upcoming, due or overdue, from age and doses given
+//! - Recall lists of patients with doses overdue, for outreach and portal reminders
+
+#[path = "immunization.controller.rs"]
+pub mod immunization_controller;
+#[path = "immunization.service.rs"]
+pub mod immunization_service;
+#[path = "immunization.forecast.rs"]
+pub mod immunization_forecast;
+#[path = "immunization.schedules.rs"]
+pub mod immunization_schedules;
+#[path = "immunization.sql.rs"]
+pub mod immunization_sql;
+
+pub use immunization_controller::ImmunizationController;
+pub use immunization_forecast::{forecast, AdministeredDose, DoseStatus, SeriesForecast};
+pub use immunization_schedules::{builtin_schedules, AgeSpan, ImmunizationSchedule, ScheduledDose, VaccineSeries};
+pub use immunization_service::{Immunization, ImmunizationConfig, ImmunizationService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Immunization Module Configuration
+pub struct ImmunizationModule {
+    pub service: Arc<ImmunizationService>,
+    pub controller: Arc<ImmunizationController>,
+}
+
+impl ImmunizationModule {
+    /// Create a new Immunization Module with dependency injection
+    pub fn new(db_pool: PgPool, config: ImmunizationConfig) -> Self {
+        let service = Arc::new(ImmunizationService::new(db_pool, config));
+        let controller = Arc::new(ImmunizationController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<ImmunizationService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,285 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::integration::integration_filedrop::{FileDropService, FileImport, FileImportStatus};
+use crate::modules::integration::integration_pipeline::TransformStep;
+use crate::modules::integration::integration_service::{ChannelMessageRequest, ChannelRequest, IntegrationChannel};
+use crate::modules::integration::IntegrationService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+#[derive(Debug, Deserialize)]
+pub struct FileImportQuery {
+    pub status: Option<FileImportStatus>,
+    #[serde(rename = "_count")]
+    pub count: Option<i64>,
+    #[serde(rename = "_offset")]
+    pub offset: Option<i64>,
+}
+
+/// Integration controller for channel configuration and file drops
+pub struct IntegrationController {
+    integration_service: Arc<IntegrationService>,
+    file_drop_service: Arc<FileDropService>,
+}
+
+impl IntegrationController {
+    /// Create new controller with injected services
+    pub fn new(integration_service: Arc<IntegrationService>, file_drop_service: Arc<FileDropService>) -> Self {
+        Self {
+            integration_service,
+            file_drop_service,
+        }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_channel, "Create integration channel")
+            .get("/", Self::list_channels, "List integration channels")
+            .get("/transforms", Self::list_transforms, "List transformation steps")
+            .get("/:id", Self::get_channel, "Get integration channel by ID")
+            .put("/:id", Self::update_channel, "Update integration channel")
+            .delete("/:id", Self::delete_channel, "Delete integration channel")
+            .post("/:id/enable", Self::enable_channel, "Enable integration channel")
+            .post("/:id/disable", Self::disable_channel, "Disable integration channel")
+            .post("/:id/messages", Self::send_message, "Queue a message on an integration channel")
+            .with_state(self.integration_service.clone())
+            .merge(
+                ApiRouter::new()
+                    .get("/:id/files", Self::list_files, "List files processed from a file drop")
+                    .post("/:id/poll", Self::poll_files, "Poll a file drop now")
+                    .with_state(self.file_drop_service.clone()),
+            )
+    }
+
+    /// Create new channel
+    pub async fn create_channel(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+        Json(payload): Json<ChannelRequest>,
+    ) -> Result<(StatusCode, Json<IntegrationChannel>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Creating integration channel {}", payload.name);
+
+        match integration_service.create_channel(actor, payload).await {
+            Ok(channel) => Ok((StatusCode::CREATED, Json(channel))),
+            Err(e) => Err(Self::error_response("Failed to create integration channel", e)),
+        }
+    }
+
+    /// List channels
+    pub async fn list_channels(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<IntegrationChannel>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match integration_service.list_channels().await {
+            Ok(channels) => Ok(Json(channels)),
+            Err(e) => Err(Self::error_response("Failed to list integration channels", e)),
+        }
+    }
+
+    /// Transformation steps a channel pipeline can use, by protocol
+    pub async fn list_transforms(headers: HeaderMap) -> Result<Json<Value>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        let steps: Vec<Value> = TransformStep::ALL
+            .iter()
+            .map(|step| json!({ "name": step.as_str(), "protocol": step.protocol() }))
+            .collect();
+        Ok(Json(json!(steps)))
+    }
+
+    /// Get channel by ID
+    pub async fn get_channel(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<IntegrationChannel>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match integration_service.get_channel(id).await {
+            Ok(Some(channel)) => Ok(Json(channel)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve integration channel", e)),
+        }
+    }
+
+    /// Update channel; the interface engine picks the change up right away
+    pub async fn update_channel(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<ChannelRequest>,
+    ) -> Result<Json<IntegrationChannel>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Updating integration channel: {}", id);
+
+        match integration_service.update_channel(id, payload).await {
+            Ok(Some(channel)) => Ok(Json(channel)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update integration channel", e)),
+        }
+    }
+
+    /// Delete channel along with its queued messages
+    pub async fn delete_channel(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Deleting integration channel: {}", id);
+
+        match integration_service.delete_channel(id).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to delete integration channel", e)),
+        }
+    }
+
+    /// Enable channel, resuming its queued messages
+    pub async fn enable_channel(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<IntegrationChannel>, (StatusCode, Json<ErrorResponse>)> {
+        Self::set_enabled(integration_service, headers, id, true).await
+    }
+
+    /// Disable channel; its messages stay queued until it is enabled
+    pub async fn disable_channel(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<IntegrationChannel>, (StatusCode, Json<ErrorResponse>)> {
+        Self::set_enabled(integration_service, headers, id, false).await
+    }
+
+    async fn set_enabled(
+        integration_service: Arc<IntegrationService>,
+        headers: HeaderMap,
+        id: Uuid,
+        enabled: bool,
+    ) -> Result<Json<IntegrationChannel>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Setting integration channel {} enabled: {}", id, enabled);
+
+        match integration_service.set_enabled(id, enabled).await {
+            Ok(Some(channel)) => Ok(Json(channel)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update integration channel", e)),
+        }
+    }
+
+    /// Queue a message to send through the channel
+    pub async fn send_message(
+        State(integration_service): State<Arc<IntegrationService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<ChannelMessageRequest>,
+    ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match integration_service.enqueue(id, payload).await {
+            Ok(Some(message_id)) => Ok((StatusCode::ACCEPTED, Json(json!({ "message_id": message_id })))),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to queue message", e)),
+        }
+    }
+
+    /// Files processed from a channel's drop, newest first
+    pub async fn list_files(
+        State(file_drop_service): State<Arc<FileDropService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Query(query): Query<FileImportQuery>,
+    ) -> Result<Json<Vec<FileImport>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match file_drop_service.list_imports(id, query.status, query.count, query.offset).await {
+            Ok(imports) => Ok(Json(imports)),
+            Err(e) => Err(Self::error_response("Failed to list processed files", e)),
+        }
+    }
+
+    /// Pick up the files waiting in a channel's drop without waiting for
+    /// the next poll
+    pub async fn poll_files(
+        State(file_drop_service): State<Arc<FileDropService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<FileImport>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Polling file drop of integration channel: {}", id);
+
+        match file_drop_service.poll_now(id).await {
+            Ok(Some(imports)) => Ok(Json(imports)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to poll file drop", e)),
+        }
+    }
+
+    /// Get authenticated user from headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(id: Uuid) -> (StatusCode, Json<ErrorResponse>) {
+        tracing::warn!("Integration channel not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Integration channel not found".to_string(),
+                message: format!("Integration channel with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        if status == StatusCode::FORBIDDEN {
+            tracing::warn!("{}: {}", context, e);
+        } else {
+            tracing::error!("{}: {}", context, e);
+        }
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
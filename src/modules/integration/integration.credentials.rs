@@ -0,0 +1,87 @@
+use base64::{engine::general_purpose, Engine as _};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::BTreeMap;
+
+use crate::core::HimsError;
+
+/// Secrets of a channel, e.g. `password` or `client_secret`
+pub type Credentials = BTreeMap<String, String>;
+
+/// Seals channel credentials at rest with AES-256-GCM. The key comes from
+/// `INTEGRATION_CREDENTIALS_KEY`, 32 bytes base64-encoded; without it no
+/// credentials can be stored or read.
+#[derive(Clone)]
+pub struct CredentialCipher {
+    key: Option<[u8; 32]>,
+}
+
+impl CredentialCipher {
+    pub fn new(key: Option<[u8; 32]>) -> Self {
+        Self { key }
+    }
+
+    pub fn from_env() -> Self {
+        let key = std::env::var("INTEGRATION_CREDENTIALS_KEY").ok().and_then(|encoded| {
+            let key = general_purpose::STANDARD
+                .decode(encoded.trim())
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+            if key.is_none() {
+                tracing::error!("INTEGRATION_CREDENTIALS_KEY is not 32 base64-encoded bytes; channel credentials are unavailable");
+            }
+            key
+        });
+        Self { key }
+    }
+
+    fn key(&self) -> Result<LessSafeKey, HimsError> {
+        let key = self.key.ok_or_else(|| HimsError::ConfigurationError {
+            message: "INTEGRATION_CREDENTIALS_KEY is not set, so channel credentials cannot be used".to_string(),
+        })?;
+        let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| HimsError::InternalError {
+            message: "Invalid credentials key".to_string(),
+        })?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    /// Nonce followed by the sealed JSON of `credentials`
+    pub fn seal(&self, credentials: &Credentials) -> Result<Vec<u8>, HimsError> {
+        let key = self.key()?;
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(|_| HimsError::InternalError {
+            message: "Failed to generate nonce".to_string(),
+        })?;
+
+        let mut sealed = serde_json::to_vec(credentials).map_err(|e| HimsError::InternalError { message: e.to_string() })?;
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+            .map_err(|_| HimsError::InternalError {
+                message: "Failed to seal credentials".to_string(),
+            })?;
+
+        let mut stored = nonce.to_vec();
+        stored.extend_from_slice(&sealed);
+        Ok(stored)
+    }
+
+    pub fn open(&self, stored: &[u8]) -> Result<Credentials, HimsError> {
+        let key = self.key()?;
+        let tampered = || HimsError::SecurityError {
+            message: "Channel credentials could not be opened; the key may have changed".to_string(),
+        };
+        if stored.len() < NONCE_LEN {
+            return Err(tampered());
+        }
+        let (nonce, sealed) = stored.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| tampered())?;
+        let mut sealed = sealed.to_vec();
+        let plain = key.open_in_place(nonce, Aad::empty(), &mut sealed).map_err(|_| tampered())?;
+        serde_json::from_slice(plain).map_err(|_| tampered())
+    }
+}
+
+impl std::fmt::Debug for CredentialCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialCipher").field("configured", &self.key.is_some()).finish()
+    }
+}
@@ -0,0 +1,672 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::models::{MedicalRecord, Reference};
+use crate::modules::auth::AuthContext;
+use crate::modules::events::EventBus;
+use crate::modules::integration::integration_credentials::Credentials;
+use crate::modules::integration::integration_files::{self, FileFormat, ImportRecord};
+use crate::modules::integration::integration_service::{ChannelEndpoint, IntegrationService, LoadedChannel};
+use crate::modules::medical_record::MedicalRecordService;
+use crate::modules::tenant::TenantService;
+
+// Import SQL queries from separate file
+use crate::modules::integration::integration_sql::*;
+
+/// Files modified more recently than this may still be uploading
+const SETTLE_TIME: Duration = Duration::from_secs(30);
+
+/// Files taken from a drop per poll; the rest wait for the next one
+const FILES_PER_POLL: usize = 50;
+
+/// Files larger than this are failed without being read
+const MAX_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileImportStatus {
+    /// Every record in the file was filed
+    Imported,
+    /// Some records were filed, the others are listed in the errors
+    Partial,
+    /// Nothing was filed
+    Failed,
+    /// Same contents as a file already imported on the channel
+    Duplicate,
+}
+
+impl FileImportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileImportStatus::Imported => "imported",
+            FileImportStatus::Partial => "partial",
+            FileImportStatus::Failed => "failed",
+            FileImportStatus::Duplicate => "duplicate",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "imported" => Some(FileImportStatus::Imported),
+            "partial" => Some(FileImportStatus::Partial),
+            "failed" => Some(FileImportStatus::Failed),
+            "duplicate" => Some(FileImportStatus::Duplicate),
+            _ => None,
+        }
+    }
+
+    /// Archive subdirectory files with this outcome are moved to
+    fn archive_dir(&self) -> &'static str {
+        match self {
+            FileImportStatus::Imported | FileImportStatus::Partial => "",
+            FileImportStatus::Failed => "failed",
+            FileImportStatus::Duplicate => "duplicate",
+        }
+    }
+}
+
+/// What became of a dropped file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileImport {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub file_name: String,
+    pub format: Option<FileFormat>,
+    pub checksum: String,
+    pub size_bytes: i64,
+    pub status: FileImportStatus,
+    pub records_imported: i32,
+    pub records_failed: i32,
+    pub errors: Vec<String>,
+    pub archived_to: Option<String>,
+    pub processed_at: DateTime<Utc>,
+}
+
+/// A file read from a drop
+#[derive(Debug, Clone)]
+pub struct DroppedFile {
+    pub name: String,
+    /// Contents, or why they could not be read
+    pub contents: Result<Vec<u8>, String>,
+}
+
+/// A drop directory, on an SFTP server or when no host is configured on
+/// this machine. Its operations block and run on the blocking pool.
+#[derive(Debug, Clone)]
+pub struct DropSource {
+    host: Option<String>,
+    // Read by the SFTP client only
+    #[cfg_attr(not(feature = "sftp"), allow(dead_code))]
+    port: u16,
+    #[cfg_attr(not(feature = "sftp"), allow(dead_code))]
+    host_key_sha256: Option<String>,
+    path: String,
+    archive_path: String,
+    #[cfg_attr(not(feature = "sftp"), allow(dead_code))]
+    credentials: Credentials,
+}
+
+impl DropSource {
+    pub fn new(endpoint: &ChannelEndpoint, credentials: Credentials) -> Option<Self> {
+        let ChannelEndpoint::Sftp { host, port, path, archive_path, host_key_sha256 } = endpoint else {
+            return None;
+        };
+        Some(Self {
+            host: host.clone().filter(|host| !host.is_empty()),
+            port: port.unwrap_or(22),
+            host_key_sha256: host_key_sha256.clone(),
+            path: path.trim_end_matches('/').to_string(),
+            archive_path: archive_path
+                .clone()
+                .unwrap_or_else(|| format!("{}/archive", path.trim_end_matches('/'))),
+            credentials,
+        })
+    }
+
+    /// Whether a directory entry is a file to pick up. Hidden and partial
+    /// uploads are left alone, as are files still being written.
+    fn wanted(name: &str, modified: Option<SystemTime>) -> bool {
+        let partial = [".tmp", ".part", ".filepart", ".partial"].iter().any(|suffix| name.ends_with(suffix));
+        let settled = modified
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_none_or(|age| age >= SETTLE_TIME);
+        !name.starts_with('.') && !partial && settled
+    }
+
+    /// Read up to `limit` files, oldest first
+    pub fn fetch(&self, limit: usize) -> Result<Vec<DroppedFile>, HimsError> {
+        match &self.host {
+            None => self.fetch_local(limit),
+            Some(host) => self.fetch_sftp(host, limit),
+        }
+    }
+
+    /// Move processed files into the archive, each under its outcome's
+    /// subdirectory. Returns where each ended up.
+    pub fn archive(&self, files: &[(String, FileImportStatus)]) -> Result<Vec<Result<String, String>>, HimsError> {
+        let stamp = Utc::now().format("%Y%m%d%H%M%S");
+        let moves: Vec<(String, String)> = files
+            .iter()
+            .map(|(name, status)| {
+                let directory = match status.archive_dir() {
+                    "" => self.archive_path.clone(),
+                    subdirectory => format!("{}/{}", self.archive_path, subdirectory),
+                };
+                (format!("{}/{}", self.path, name), format!("{}/{}-{}", directory, stamp, name))
+            })
+            .collect();
+        match &self.host {
+            None => Ok(Self::archive_local(&moves)),
+            Some(host) => self.archive_sftp(host, &moves),
+        }
+    }
+
+    fn fetch_local(&self, limit: usize) -> Result<Vec<DroppedFile>, HimsError> {
+        let entries = std::fs::read_dir(&self.path).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Cannot read drop directory {}: {}", self.path, e),
+        })?;
+        let mut files: Vec<(SystemTime, String, u64)> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+                let name = entry.file_name().into_string().ok()?;
+                let modified = metadata.modified().ok();
+                Self::wanted(&name, modified).then_some((modified.unwrap_or(SystemTime::UNIX_EPOCH), name, metadata.len()))
+            })
+            .collect();
+        files.sort();
+
+        Ok(files
+            .into_iter()
+            .take(limit)
+            .map(|(_, name, size)| {
+                let contents = if size > MAX_FILE_SIZE {
+                    Err(format!("File is larger than {} bytes", MAX_FILE_SIZE))
+                } else {
+                    std::fs::read(Path::new(&self.path).join(&name)).map_err(|e| e.to_string())
+                };
+                DroppedFile { name, contents }
+            })
+            .collect())
+    }
+
+    fn archive_local(moves: &[(String, String)]) -> Vec<Result<String, String>> {
+        moves
+            .iter()
+            .map(|(from, to)| {
+                if let Some(directory) = Path::new(to).parent() {
+                    std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+                }
+                std::fs::rename(from, to).map_err(|e| e.to_string())?;
+                Ok(to.clone())
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "sftp")]
+    fn connect(&self, host: &str) -> Result<ssh2::Sftp, HimsError> {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let failed = |e: &dyn std::fmt::Display| HimsError::NetworkError {
+            message: format!("SFTP connection to {}:{} failed: {}", host, self.port, e),
+        };
+        let tcp = std::net::TcpStream::connect((host, self.port)).map_err(|e| failed(&e))?;
+        let mut session = ssh2::Session::new().map_err(|e| failed(&e))?;
+        session.set_timeout(30_000);
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(|e| failed(&e))?;
+
+        if let Some(expected) = &self.host_key_sha256 {
+            let presented = session
+                .host_key_hash(ssh2::HashType::Sha256)
+                .map(|hash| general_purpose::STANDARD_NO_PAD.encode(hash))
+                .unwrap_or_default();
+            if presented != expected.trim_end_matches('=') {
+                return Err(HimsError::SecurityError {
+                    message: format!("SFTP server {} presented an unexpected host key", host),
+                });
+            }
+        }
+
+        let username = self.credentials.get("username").map(String::as_str).unwrap_or_default();
+        match (self.credentials.get("private_key"), self.credentials.get("password")) {
+            (Some(key), _) => session.userauth_pubkey_memory(
+                username,
+                None,
+                key,
+                self.credentials.get("passphrase").map(String::as_str),
+            ),
+            (None, Some(password)) => session.userauth_password(username, password),
+            (None, None) => {
+                return Err(HimsError::ConfigurationError {
+                    message: "SFTP channels need a password or private_key credential".to_string(),
+                })
+            }
+        }
+        .map_err(|e| HimsError::AuthenticationError {
+            message: format!("SFTP login to {} failed: {}", host, e),
+        })?;
+        session.sftp().map_err(|e| failed(&e))
+    }
+
+    #[cfg(feature = "sftp")]
+    fn fetch_sftp(&self, host: &str, limit: usize) -> Result<Vec<DroppedFile>, HimsError> {
+        use std::io::Read;
+
+        let sftp = self.connect(host)?;
+        let entries = sftp.readdir(Path::new(&self.path)).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Cannot read drop directory {}: {}", self.path, e),
+        })?;
+        let mut files: Vec<(u64, String, u64)> = entries
+            .into_iter()
+            .filter(|(_, stat)| stat.is_file())
+            .filter_map(|(path, stat)| {
+                let name = path.file_name()?.to_str()?.to_string();
+                let modified = stat.mtime.map(|mtime| SystemTime::UNIX_EPOCH + Duration::from_secs(mtime));
+                Self::wanted(&name, modified).then_some((stat.mtime.unwrap_or_default(), name, stat.size.unwrap_or_default()))
+            })
+            .collect();
+        files.sort();
+
+        Ok(files
+            .into_iter()
+            .take(limit)
+            .map(|(_, name, size)| {
+                let contents = if size > MAX_FILE_SIZE {
+                    Err(format!("File is larger than {} bytes", MAX_FILE_SIZE))
+                } else {
+                    let mut contents = Vec::new();
+                    match sftp.open(Path::new(&self.path).join(&name)) {
+                        Ok(mut file) => file.read_to_end(&mut contents).map(|_| contents).map_err(|e| e.to_string()),
+                        Err(e) => Err(e.to_string()),
+                    }
+                };
+                DroppedFile { name, contents }
+            })
+            .collect())
+    }
+
+    #[cfg(feature = "sftp")]
+    fn archive_sftp(&self, host: &str, moves: &[(String, String)]) -> Result<Vec<Result<String, String>>, HimsError> {
+        let sftp = self.connect(host)?;
+        Ok(moves
+            .iter()
+            .map(|(from, to)| {
+                let to_path = Path::new(to);
+                if let Some(directory) = to_path.parent() {
+                    // Fails when the directory exists, which is fine
+                    let _ = sftp.mkdir(directory, 0o750);
+                }
+                sftp.rename(Path::new(from), to_path, None).map_err(|e| e.to_string())?;
+                Ok(to.clone())
+            })
+            .collect())
+    }
+
+    #[cfg(not(feature = "sftp"))]
+    fn fetch_sftp(&self, host: &str, _limit: usize) -> Result<Vec<DroppedFile>, HimsError> {
+        Err(Self::sftp_unavailable(host))
+    }
+
+    #[cfg(not(feature = "sftp"))]
+    fn archive_sftp(&self, host: &str, _moves: &[(String, String)]) -> Result<Vec<Result<String, String>>, HimsError> {
+        Err(Self::sftp_unavailable(host))
+    }
+
+    #[cfg(not(feature = "sftp"))]
+    fn sftp_unavailable(host: &str) -> HimsError {
+        HimsError::ConfigurationError {
+            message: format!("Cannot reach SFTP server {}: built without the sftp feature", host),
+        }
+    }
+}
+
+/// Picks up files dropped on inbound SFTP channels, files their records
+/// against the patients they name, archives them and records the outcome
+/// of each
+pub struct FileDropService {
+    pool: PgPool,
+    integration: Arc<IntegrationService>,
+    medical_records: MedicalRecordService,
+    tenants: TenantService,
+}
+
+impl FileDropService {
+    pub fn new(pool: PgPool, integration: Arc<IntegrationService>, events: EventBus) -> Self {
+        Self {
+            medical_records: MedicalRecordService::with_events(pool.clone(), events),
+            tenants: TenantService::new(pool.clone()),
+            pool,
+            integration,
+        }
+    }
+
+    /// Files processed on a channel, newest first
+    pub async fn list_imports(
+        &self,
+        channel_id: Uuid,
+        status: Option<FileImportStatus>,
+        count: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<FileImport>, HimsError> {
+        sqlx::query(LIST_FILE_IMPORTS)
+            .bind(channel_id)
+            .bind(status.map(|status| status.as_str()))
+            .bind(count.unwrap_or(50).clamp(1, 500))
+            .bind(offset.unwrap_or(0).max(0))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_import)
+            .collect()
+    }
+
+    /// Poll one channel of the current tenant now. `None` when there is no
+    /// such channel.
+    pub async fn poll_now(&self, channel_id: Uuid) -> Result<Option<Vec<FileImport>>, HimsError> {
+        // Looked up as the caller first, so other tenants' channels stay hidden
+        let Some(channel) = self.integration.get_channel(channel_id).await? else {
+            return Ok(None);
+        };
+        if !matches!(channel.endpoint, ChannelEndpoint::Sftp { .. }) {
+            return Err(HimsError::ValidationError {
+                message: format!("Channel {} is not a file drop", channel.name),
+            });
+        }
+        match self.integration.reload_channel(channel_id).await? {
+            Some(loaded) => self.poll_channel(&loaded).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Poll every enabled file drop once. Returns how many files were
+    /// processed.
+    pub async fn poll_once(&self) -> usize {
+        let mut processed = 0;
+        for loaded in self.integration.loaded_channels() {
+            if !loaded.channel.enabled || !matches!(loaded.channel.endpoint, ChannelEndpoint::Sftp { .. }) {
+                continue;
+            }
+            match self.poll_channel(&loaded).await {
+                Ok(imports) => processed += imports.len(),
+                Err(e) => tracing::error!("Failed to poll file drop {}: {}", loaded.channel.name, e),
+            }
+        }
+        processed
+    }
+
+    /// Process the files waiting in a channel's drop, as the channel's
+    /// tenant. Skipped while another server is polling the same channel.
+    pub async fn poll_channel(&self, loaded: &LoadedChannel) -> Result<Vec<FileImport>, HimsError> {
+        let credentials = loaded.credentials.clone().map_err(|message| HimsError::ConfigurationError { message })?;
+        let Some(source) = DropSource::new(&loaded.channel.endpoint, credentials) else {
+            return Ok(Vec::new());
+        };
+        let context = match with_tenant(TenantContext::system(), self.tenants.get_tenant(loaded.tenant_id)).await? {
+            Some(tenant) => tenant.context(),
+            None => TenantContext::new(loaded.tenant_id, None),
+        };
+
+        with_tenant(context, async {
+            let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+            let mut lock = self.pool.begin().await.map_err(db)?;
+            let locked: bool = sqlx::query_scalar(TRY_LOCK_CHANNEL)
+                .bind(loaded.channel.id)
+                .fetch_one(&mut *lock)
+                .await
+                .map_err(db)?;
+            if !locked {
+                return Ok(Vec::new());
+            }
+
+            let fetch = source.clone();
+            let files = tokio::task::spawn_blocking(move || fetch.fetch(FILES_PER_POLL))
+                .await
+                .map_err(|e| HimsError::InternalError { message: e.to_string() })??;
+            if files.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let mut outcomes = Vec::with_capacity(files.len());
+            for file in &files {
+                outcomes.push(self.process(loaded, file).await?);
+            }
+
+            let moves: Vec<(String, FileImportStatus)> =
+                outcomes.iter().map(|outcome| (outcome.file_name.clone(), outcome.status)).collect();
+            let archive = source.clone();
+            let archived = tokio::task::spawn_blocking(move || archive.archive(&moves))
+                .await
+                .map_err(|e| HimsError::InternalError { message: e.to_string() })?
+                .unwrap_or_else(|e| vec![Err(e.to_string()); outcomes.len()]);
+
+            let mut imports = Vec::with_capacity(outcomes.len());
+            for (mut outcome, archived) in outcomes.into_iter().zip(archived) {
+                match archived {
+                    Ok(path) => outcome.archived_to = Some(path),
+                    // Left in the drop, the file is seen again next poll
+                    // and recorded as a duplicate if it was imported
+                    Err(e) => outcome.errors.push(format!("Not archived: {}", e)),
+                }
+                imports.push(self.record(loaded, &outcome).await?);
+            }
+            lock.commit().await.map_err(db)?;
+
+            for import in &imports {
+                tracing::info!(
+                    "File {} on channel {}: {} ({} filed, {} failed)",
+                    import.file_name,
+                    loaded.channel.name,
+                    import.status.as_str(),
+                    import.records_imported,
+                    import.records_failed
+                );
+            }
+            Ok(imports)
+        })
+        .await
+    }
+
+    /// Parse a file and file its records. The outcome is not stored yet.
+    async fn process(&self, loaded: &LoadedChannel, file: &DroppedFile) -> Result<FileImport, HimsError> {
+        let contents = file.contents.as_deref().unwrap_or_default();
+        let mut outcome = FileImport {
+            id: Uuid::new_v4(),
+            channel_id: loaded.channel.id,
+            file_name: file.name.clone(),
+            format: None,
+            checksum: format!("{:x}", Sha256::digest(contents)),
+            size_bytes: contents.len() as i64,
+            status: FileImportStatus::Failed,
+            records_imported: 0,
+            records_failed: 0,
+            errors: Vec::new(),
+            archived_to: None,
+            processed_at: Utc::now(),
+        };
+        if let Err(e) = &file.contents {
+            outcome.errors.push(format!("Could not read file: {}", e));
+            return Ok(outcome);
+        }
+
+        let duplicate: bool = sqlx::query_scalar(FILE_ALREADY_IMPORTED)
+            .bind(loaded.channel.id)
+            .bind(&outcome.checksum)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        if duplicate {
+            outcome.status = FileImportStatus::Duplicate;
+            return Ok(outcome);
+        }
+
+        let Ok(text) = std::str::from_utf8(contents) else {
+            outcome.errors.push("File is not UTF-8 text".to_string());
+            return Ok(outcome);
+        };
+        let Some(format) = FileFormat::detect(&file.name, text) else {
+            outcome.errors.push("Not an HL7, CSV or CCD file".to_string());
+            return Ok(outcome);
+        };
+        outcome.format = Some(format);
+
+        for record in integration_files::parse(format, text) {
+            match record {
+                Ok(record) => match self.file_record(loaded, &record).await {
+                    Ok(()) => outcome.records_imported += 1,
+                    Err(e) => {
+                        outcome.records_failed += 1;
+                        outcome.errors.push(format!("{}: {}", record.position, e));
+                    }
+                },
+                Err(e) => {
+                    outcome.records_failed += 1;
+                    outcome.errors.push(e);
+                }
+            }
+        }
+        outcome.status = match (outcome.records_imported, outcome.records_failed) {
+            (0, _) => FileImportStatus::Failed,
+            (_, 0) => FileImportStatus::Imported,
+            _ => FileImportStatus::Partial,
+        };
+        Ok(outcome)
+    }
+
+    /// File a record as a preliminary medical record of the patient whose
+    /// identifier it carries, authored by the channel
+    async fn file_record(&self, loaded: &LoadedChannel, record: &ImportRecord) -> Result<(), HimsError> {
+        let patients: Vec<Uuid> = sqlx::query_scalar(FIND_PATIENTS_BY_IDENTIFIER_VALUE)
+            .bind(&record.patient_identifier)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let patient_id = match patients.as_slice() {
+            [patient_id] => *patient_id,
+            [] => {
+                return Err(HimsError::ValidationError {
+                    message: format!("No patient with identifier {}", record.patient_identifier),
+                })
+            }
+            _ => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Several patients have identifier {}", record.patient_identifier),
+                })
+            }
+        };
+
+        let author = Reference {
+            reference: format!("Endpoint/{}", loaded.channel.id),
+            display: Some(loaded.channel.name.clone()),
+        };
+        let medical_record = MedicalRecord::new(patient_id, record.record_type.clone(), record.content.clone(), author);
+        let actor = loaded.created_by.map(AuthContext::new);
+        self.medical_records.create_medical_record(&medical_record, actor.as_ref()).await?;
+        Ok(())
+    }
+
+    async fn record(&self, loaded: &LoadedChannel, outcome: &FileImport) -> Result<FileImport, HimsError> {
+        let row = sqlx::query(INSERT_FILE_IMPORT)
+            .bind(outcome.id)
+            .bind(loaded.tenant_id)
+            .bind(outcome.channel_id)
+            .bind(&outcome.file_name)
+            .bind(outcome.format.map(|format| format.as_str()))
+            .bind(&outcome.checksum)
+            .bind(outcome.size_bytes)
+            .bind(outcome.status.as_str())
+            .bind(outcome.records_imported)
+            .bind(outcome.records_failed)
+            .bind(json!(outcome.errors))
+            .bind(&outcome.archived_to)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Self::row_to_import(&row)
+    }
+
+    /// Poll every file drop each `interval`, across tenants
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.poll_once().await;
+            }
+        }))
+    }
+
+    fn row_to_import(row: &PgRow) -> Result<FileImport, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let format: Option<String> = row.try_get("format").map_err(read)?;
+        let status: String = row.try_get("status").map_err(read)?;
+        let errors: serde_json::Value = row.try_get("errors").map_err(read)?;
+        Ok(FileImport {
+            id: row.try_get("id").map_err(read)?,
+            channel_id: row.try_get("channel_id").map_err(read)?,
+            file_name: row.try_get("file_name").map_err(read)?,
+            format: format.as_deref().and_then(FileFormat::from_string),
+            checksum: row.try_get("checksum").map_err(read)?,
+            size_bytes: row.try_get("size_bytes").map_err(read)?,
+            status: FileImportStatus::from_string(&status)
+                .ok_or_else(|| HimsError::DatabaseError(format!("Unknown file import status {}", status)))?,
+            records_imported: row.try_get("records_imported").map_err(read)?,
+            records_failed: row.try_get("records_failed").map_err(read)?,
+            errors: serde_json::from_value(errors).unwrap_or_default(),
+            archived_to: row.try_get("archived_to").map_err(read)?,
+            processed_at: row.try_get("processed_at").map_err(read)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_drop(directory: &Path) -> DropSource {
+        let endpoint = ChannelEndpoint::Sftp {
+            host: None,
+            port: None,
+            path: directory.to_string_lossy().to_string(),
+            archive_path: None,
+            host_key_sha256: None,
+        };
+        DropSource::new(&endpoint, Credentials::new()).unwrap()
+    }
+
+    #[test]
+    fn test_local_drop_fetch_and_archive() {
+        let directory = tempfile::tempdir().unwrap();
+        let settled = SystemTime::now() - Duration::from_secs(120);
+        for name in ["results.hl7", ".hidden.hl7", "upload.csv.part"] {
+            let path = directory.path().join(name);
+            std::fs::write(&path, "MSH|^~\\&|LAB\r").unwrap();
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(settled).unwrap();
+        }
+        std::fs::write(directory.path().join("uploading.csv"), "mrn,test,value\n").unwrap();
+
+        let source = local_drop(directory.path());
+        let files = source.fetch(10).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "results.hl7");
+        assert_eq!(files[0].contents.as_deref().unwrap(), b"MSH|^~\\&|LAB\r");
+
+        let archived = source.archive(&[("results.hl7".to_string(), FileImportStatus::Failed)]).unwrap();
+        let archived = archived[0].as_ref().unwrap();
+        assert!(archived.contains("/archive/failed/"));
+        assert!(Path::new(archived).exists());
+        assert!(!directory.path().join("results.hl7").exists());
+    }
+}
@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::MedicalRecordType;
+use crate::standards::hl7v2::parser::{Hl7Parser, Hl7Segment};
+use crate::standards::ucum::Ucum;
+
+/// Format of a dropped file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    /// One or more HL7 v2 result messages, with or without FHS/BHS batch
+    /// envelopes
+    Hl7,
+    /// One result per row under a header row
+    Csv,
+    /// C-CDA Continuity of Care Document or another CDA document
+    Ccd,
+}
+
+impl FileFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Hl7 => "hl7",
+            FileFormat::Csv => "csv",
+            FileFormat::Ccd => "ccd",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "hl7" => Some(FileFormat::Hl7),
+            "csv" => Some(FileFormat::Csv),
+            "ccd" => Some(FileFormat::Ccd),
+            _ => None,
+        }
+    }
+
+    /// Format from the file's extension, or failing that its first bytes
+    pub fn detect(file_name: &str, contents: &str) -> Option<Self> {
+        let extension = file_name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("hl7") => return Some(FileFormat::Hl7),
+            Some("csv") => return Some(FileFormat::Csv),
+            Some("ccd") | Some("cda") => return Some(FileFormat::Ccd),
+            _ => {}
+        }
+
+        let start = contents.trim_start_matches('\u{feff}').trim_start();
+        if start.starts_with("MSH|") || start.starts_with("FHS|") || start.starts_with("BHS|") {
+            Some(FileFormat::Hl7)
+        } else if start.starts_with('<') && start.contains("ClinicalDocument") {
+            Some(FileFormat::Ccd)
+        } else {
+            None
+        }
+    }
+}
+
+/// A record found in a file, to be filed against the patient it names
+#[derive(Debug, Clone)]
+pub struct ImportRecord {
+    /// Where in the file the record is, e.g. `message 2` or `row 14`
+    pub position: String,
+    /// Patient identifier value (MRN) the sender used
+    pub patient_identifier: String,
+    pub record_type: MedicalRecordType,
+    pub content: String,
+}
+
+/// Records of a file, or why each unreadable part could not be read
+pub fn parse(format: FileFormat, contents: &str) -> Vec<Result<ImportRecord, String>> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    match format {
+        FileFormat::Hl7 => parse_hl7(contents),
+        FileFormat::Csv => parse_csv(contents),
+        FileFormat::Ccd => vec![parse_ccd(contents)],
+    }
+}
+
+fn field(segment: &Hl7Segment, index: usize) -> &str {
+    segment.fields.get(index).map(String::as_str).unwrap_or_default()
+}
+
+/// Text of a coded element: its text component, else its code
+fn coded_text(value: &str) -> &str {
+    let mut components = value.split('^');
+    let code = components.next().unwrap_or_default();
+    components.next().filter(|text| !text.is_empty()).unwrap_or(code)
+}
+
+/// Check that a result's unit (code, text, coding system) is valid UCUM
+/// when it is coded in UCUM or not coded at all; locally coded units are
+/// left to the sender
+fn check_unit(unit: &str) -> Result<(), String> {
+    let mut components = unit.split('^');
+    let code = components.next().unwrap_or_default();
+    let system = components.nth(1).unwrap_or_default();
+    if code.is_empty() || !(system.is_empty() || system.eq_ignore_ascii_case("UCUM")) {
+        return Ok(());
+    }
+    Ucum::parse(code).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Each message of a batch becomes a diagnostic report listing its results
+fn parse_hl7(contents: &str) -> Vec<Result<ImportRecord, String>> {
+    let parser = Hl7Parser::new();
+    let messages = parser.split_batch(contents);
+    if messages.is_empty() {
+        return vec![Err("No MSH segment found".to_string())];
+    }
+
+    messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let position = format!("message {}", index + 1);
+            let parsed = parser
+                .parse_message(message)
+                .map_err(|e| format!("{}: {}", position, e))?;
+
+            let mut patient_identifier = String::new();
+            let mut lines = Vec::new();
+            for segment in &parsed.segments {
+                match segment.segment_type.as_str() {
+                    // PID-3, first repetition, ID component
+                    "PID" => {
+                        patient_identifier = field(segment, 2)
+                            .split('~')
+                            .next()
+                            .and_then(|identifier| identifier.split('^').next())
+                            .unwrap_or_default()
+                            .to_string();
+                    }
+                    "OBR" => lines.push(coded_text(field(segment, 3)).to_string()),
+                    "OBX" => {
+                        check_unit(field(segment, 5))
+                            .map_err(|e| format!("{}: OBX {}: {}", position, field(segment, 0), e))?;
+                        let mut line = format!("  {}: {}", coded_text(field(segment, 2)), field(segment, 4));
+                        for (index, format) in [(5, " {}"), (6, " ({})"), (7, " [{}]")] {
+                            let value = coded_text(field(segment, index));
+                            if !value.is_empty() {
+                                line.push_str(&format.replace("{}", value));
+                            }
+                        }
+                        lines.push(line);
+                    }
+                    "NTE" => lines.push(format!("  Note: {}", field(segment, 2))),
+                    _ => {}
+                }
+            }
+
+            if patient_identifier.is_empty() {
+                return Err(format!("{}: no patient identifier in PID-3", position));
+            }
+            if lines.is_empty() {
+                return Err(format!("{}: no results (OBR/OBX) in {}", position, parsed.message_type));
+            }
+            Ok(ImportRecord {
+                position,
+                patient_identifier,
+                record_type: MedicalRecordType::DiagnosticReport,
+                content: lines.join("\n"),
+            })
+        })
+        .collect()
+}
+
+/// Split a CSV line, honouring double-quoted fields
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Rows become diagnostic reports. The header names the columns:
+/// `patient_identifier` (or `mrn`), `test` (or `test_name`, `test_code`),
+/// `value`, and optionally `unit`, `reference_range`, `flag` and
+/// `collected_at`.
+fn parse_csv(contents: &str) -> Vec<Result<ImportRecord, String>> {
+    let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return vec![Err("File is empty".to_string())];
+    };
+    let header: Vec<String> = csv_fields(header).iter().map(|name| name.to_ascii_lowercase()).collect();
+    let column = |names: &[&str]| header.iter().position(|name| names.contains(&name.as_str()));
+
+    let (Some(patient), Some(test), Some(value)) = (
+        column(&["patient_identifier", "mrn", "patient_id"]),
+        column(&["test", "test_name", "test_code"]),
+        column(&["value", "result"]),
+    ) else {
+        return vec![Err("Header needs patient_identifier, test and value columns".to_string())];
+    };
+    let optional = [
+        (column(&["unit", "units"]), " {}"),
+        (column(&["reference_range"]), " ({})"),
+        (column(&["flag", "abnormal_flag"]), " [{}]"),
+        (column(&["collected_at"]), ", collected {}"),
+    ];
+
+    lines
+        .map(|(index, line)| {
+            let position = format!("row {}", index + 1);
+            let fields = csv_fields(line);
+            let get = |column: usize| fields.get(column).map(String::as_str).unwrap_or_default();
+            if get(patient).is_empty() || get(test).is_empty() {
+                return Err(format!("{}: patient identifier and test are required", position));
+            }
+
+            if let Some(unit) = optional[0].0.map(get) {
+                check_unit(unit).map_err(|e| format!("{}: {}", position, e))?;
+            }
+
+            let mut content = format!("{}: {}", get(test), get(value));
+            for (column, format) in optional {
+                if let Some(value) = column.map(get).filter(|value| !value.is_empty()) {
+                    content.push_str(&format.replace("{}", value));
+                }
+            }
+            Ok(ImportRecord {
+                position,
+                patient_identifier: get(patient).to_string(),
+                record_type: MedicalRecordType::DiagnosticReport,
+                content,
+            })
+        })
+        .collect()
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+/// The document is kept whole, filed by its LOINC document type against
+/// the patient in `recordTarget/patientRole/id`
+fn parse_ccd(contents: &str) -> Result<ImportRecord, String> {
+    let document = roxmltree::Document::parse(contents).map_err(|e| format!("Invalid XML: {}", e))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "ClinicalDocument" {
+        return Err(format!("Root element is {}, not ClinicalDocument", root.tag_name().name()));
+    }
+    let patient_identifier = child(root, "recordTarget")
+        .and_then(|target| child(target, "patientRole"))
+        .and_then(|role| role.children().find(|id| id.is_element() && id.tag_name().name() == "id" && id.has_attribute("extension")))
+        .and_then(|id| id.attribute("extension"))
+        .ok_or_else(|| "No patient identifier in recordTarget/patientRole/id".to_string())?;
+    let record_type = match child(root, "code").and_then(|code| code.attribute("code")) {
+        Some("18842-5") => MedicalRecordType::DischargeSummary,
+        Some("11488-4") => MedicalRecordType::Consultation,
+        Some("11506-3") => MedicalRecordType::ProgressNote,
+        Some("11504-8") => MedicalRecordType::OperativeNote,
+        _ => MedicalRecordType::DiagnosticReport,
+    };
+
+    Ok(ImportRecord {
+        position: "document".to_string(),
+        patient_identifier: patient_identifier.to_string(),
+        record_type,
+        content: contents.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(FileFormat::detect("results.HL7", ""), Some(FileFormat::Hl7));
+        assert_eq!(FileFormat::detect("results.txt", "FHS|^~\\&|LAB\r"), Some(FileFormat::Hl7));
+        assert_eq!(FileFormat::detect("summary.xml", "<?xml version=\"1.0\"?><ClinicalDocument/>"), Some(FileFormat::Ccd));
+        assert_eq!(FileFormat::detect("notes.txt", "hello"), None);
+    }
+
+    #[test]
+    fn test_parse_hl7_batch() {
+        let batch = "FHS|^~\\&|LAB\rBHS|^~\\&|LAB\r\
+            MSH|^~\\&|LAB|MAIN|HIMS|MAIN|20240101||ORU^R01|1|P|2.5\rPID|1||MRN1^^^MAIN~99||DOE^JANE\r\
+            OBR|1|||CBC^Complete blood count\rOBX|1|NM|HGB^Hemoglobin||13.5|g/dL|12-16|N\r\
+            MSH|^~\\&|LAB|MAIN|HIMS|MAIN|20240101||ORU^R01|2|P|2.5\rPID|1||\rOBR|1|||GLU\r\
+            BTS|2\rFTS|1\r";
+
+        let records = parse(FileFormat::Hl7, batch);
+        assert_eq!(records.len(), 2);
+        let first = records[0].as_ref().unwrap();
+        assert_eq!(first.patient_identifier, "MRN1");
+        assert_eq!(first.content, "Complete blood count\n  Hemoglobin: 13.5 g/dL (12-16) [N]");
+        assert!(records[1].as_ref().unwrap_err().starts_with("message 2"));
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "MRN,Test,Value,Unit,Reference_Range\nMRN1,\"Glucose, fasting\",5.4,mmol/L,3.9-5.5\n,Glucose,5.0,,\n";
+
+        let records = parse(FileFormat::Csv, csv);
+        assert_eq!(records.len(), 2);
+        let first = records[0].as_ref().unwrap();
+        assert_eq!(first.patient_identifier, "MRN1");
+        assert_eq!(first.content, "Glucose, fasting: 5.4 mmol/L (3.9-5.5)");
+        assert_eq!(records[1].as_ref().unwrap_err(), "row 3: patient identifier and test are required");
+
+        assert!(parse(FileFormat::Csv, "a,b\n1,2\n")[0].is_err());
+    }
+
+    #[test]
+    fn test_rejects_non_ucum_units() {
+        let csv = "MRN,Test,Value,Unit\nMRN1,Potassium,4.1,mEq/L\nMRN1,Potassium,4.1,meq/L\n";
+        let records = parse(FileFormat::Csv, csv);
+        assert!(records[0].as_ref().unwrap_err().starts_with("row 2: "));
+        assert!(records[1].is_ok());
+
+        // Units coded in a local system are not checked
+        let message = "MSH|^~\\&|LAB|MAIN|HIMS|MAIN|20240101||ORU^R01|1|P|2.5\rPID|1||MRN1\r\
+            OBX|1|NM|K^Potassium||4.1|mEq/L\rOBX|2|NM|NA^Sodium||140|mEq/L^^L\r";
+        assert!(parse(FileFormat::Hl7, message)[0].as_ref().unwrap_err().starts_with("message 1: OBX 1: "));
+        let local = message.replace("OBX|1|NM|K^Potassium||4.1|mEq/L\r", "");
+        assert!(parse(FileFormat::Hl7, &local)[0].is_ok());
+    }
+
+    #[test]
+    fn test_parse_ccd() {
+        let ccd = r#"<ClinicalDocument xmlns="urn:hl7-org:v3">
+            <code code="18842-5" codeSystem="2.16.840.1.113883.6.1"/>
+            <recordTarget><patientRole><id root="2.16.840.1.113883.19.5" extension="MRN1"/></patientRole></recordTarget>
+        </ClinicalDocument>"#;
+
+        let record = parse(FileFormat::Ccd, ccd).remove(0).unwrap();
+        assert_eq!(record.patient_identifier, "MRN1");
+        assert!(matches!(record.record_type, MedicalRecordType::DischargeSummary));
+        assert!(parse(FileFormat::Ccd, "<Bundle/>")[0].is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::modules::integration::integration_service::ChannelEndpoint;
+use crate::modules::interface_engine::Protocol;
+
+/// A transformation applied to each message a channel carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransformStep {
+    /// End segments with `\r`, whether they arrived with `\n` or `\r\n`
+    Hl7NormalizeSegments,
+    /// Drop locally defined Z segments the receiver does not know
+    Hl7StripZSegments,
+    /// Set MSH-5 and MSH-6 to the receiver configured on the MLLP endpoint
+    Hl7RewriteReceiver,
+    /// Re-serialize FHIR JSON without whitespace
+    FhirMinify,
+}
+
+impl TransformStep {
+    pub const ALL: [TransformStep; 4] = [
+        TransformStep::Hl7NormalizeSegments,
+        TransformStep::Hl7StripZSegments,
+        TransformStep::Hl7RewriteReceiver,
+        TransformStep::FhirMinify,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransformStep::Hl7NormalizeSegments => "hl7-normalize-segments",
+            TransformStep::Hl7StripZSegments => "hl7-strip-z-segments",
+            TransformStep::Hl7RewriteReceiver => "hl7-rewrite-receiver",
+            TransformStep::FhirMinify => "fhir-minify",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.as_str() == s)
+    }
+
+    /// Protocol of the messages the step understands
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            TransformStep::FhirMinify => Protocol::Fhir,
+            _ => Protocol::Hl7v2,
+        }
+    }
+
+    pub fn apply(&self, payload: &str, endpoint: &ChannelEndpoint) -> Result<String, String> {
+        match self {
+            TransformStep::Hl7NormalizeSegments => {
+                let segments: Vec<&str> = payload.split(['\r', '\n']).filter(|segment| !segment.is_empty()).collect();
+                Ok(format!("{}\r", segments.join("\r")))
+            }
+            TransformStep::Hl7StripZSegments => Ok(payload
+                .split_inclusive('\r')
+                .filter(|segment| !segment.starts_with('Z'))
+                .collect()),
+            TransformStep::Hl7RewriteReceiver => {
+                let ChannelEndpoint::Mllp { receiving_application, receiving_facility, .. } = endpoint else {
+                    return Err("hl7-rewrite-receiver needs an MLLP endpoint".to_string());
+                };
+                Ok(payload
+                    .split_inclusive('\r')
+                    .map(|segment| {
+                        if !segment.starts_with("MSH|") {
+                            return segment.to_string();
+                        }
+                        let mut fields: Vec<String> = segment.split('|').map(str::to_string).collect();
+                        while fields.len() < 7 {
+                            fields.push(String::new());
+                        }
+                        if let Some(application) = receiving_application {
+                            fields[4] = application.clone();
+                        }
+                        if let Some(facility) = receiving_facility {
+                            fields[5] = facility.clone();
+                        }
+                        fields.join("|")
+                    })
+                    .collect())
+            }
+            TransformStep::FhirMinify => serde_json::from_str::<Value>(payload)
+                .map(|resource| resource.to_string())
+                .map_err(|e| format!("Payload is not JSON: {}", e)),
+        }
+    }
+}
+
+/// Run a message through each step in turn
+pub fn run(pipeline: &[TransformStep], payload: &str, endpoint: &ChannelEndpoint) -> Result<String, String> {
+    pipeline
+        .iter()
+        .try_fold(payload.to_string(), |payload, step| step.apply(&payload, endpoint))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mllp() -> ChannelEndpoint {
+        ChannelEndpoint::Mllp {
+            host: "lis.local".to_string(),
+            port: 2575,
+            receiving_application: Some("LIS".to_string()),
+            receiving_facility: None,
+        }
+    }
+
+    #[test]
+    fn test_hl7_pipeline() {
+        let message = "MSH|^~\\&|HIMS|MAIN|X|LAB|20240101||ADT^A04|1|P|2.5\r\nPID|1||42\nZPI|custom\r\n";
+        let pipeline = [
+            TransformStep::Hl7NormalizeSegments,
+            TransformStep::Hl7StripZSegments,
+            TransformStep::Hl7RewriteReceiver,
+        ];
+
+        assert_eq!(
+            run(&pipeline, message, &mllp()).unwrap(),
+            "MSH|^~\\&|HIMS|MAIN|LIS|LAB|20240101||ADT^A04|1|P|2.5\rPID|1||42\r"
+        );
+    }
+
+    #[test]
+    fn test_steps_check_their_input() {
+        let fhir = ChannelEndpoint::Fhir {
+            base_url: "https://fhir.example.org".to_string(),
+            token_url: None,
+        };
+        assert!(TransformStep::Hl7RewriteReceiver.apply("MSH|^~\\&\r", &fhir).is_err());
+        assert!(TransformStep::FhirMinify.apply("MSH|", &fhir).is_err());
+        assert_eq!(
+            TransformStep::FhirMinify.apply("{ \"resourceType\": \"Patient\" }", &fhir).unwrap(),
+            "{\"resourceType\":\"Patient\"}"
+        );
+        assert_eq!(TransformStep::from_string("hl7-strip-z-segments"), Some(TransformStep::Hl7StripZSegments));
+        assert_eq!(TransformStep::from_string("xslt"), None);
+    }
+}
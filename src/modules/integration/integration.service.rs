@@ -0,0 +1,629 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::postgres::{PgListener, PgRow};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{current_tenant_id, with_tenant, TenantContext};
+use crate::modules::integration::integration_credentials::{CredentialCipher, Credentials};
+use crate::modules::integration::integration_pipeline::{self, TransformStep};
+use crate::modules::integration::integration_transport::ChannelTransport;
+use crate::modules::interface_engine::interface_engine_service::DEFAULT_MAX_ATTEMPTS;
+use crate::modules::interface_engine::{
+    Direction, HandlerError, InterfaceEngineService, MessageHandler, NewMessage, Protocol, QueuedMessage,
+};
+
+// Import SQL queries from separate file
+use crate::modules::integration::integration_sql::*;
+
+/// Postgres channel the change trigger notifies with a channel's ID
+const CHANGE_NOTIFICATIONS: &str = "integration_channels";
+
+/// How often every channel is reloaded, in case a notification was missed
+const FULL_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where a channel connects and how, by type. Secrets live in the
+/// channel's credentials, never here.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChannelEndpoint {
+    /// HL7 v2 receiver over MLLP
+    Mllp {
+        host: String,
+        port: u16,
+        /// MSH-5 and MSH-6 set by the `hl7-rewrite-receiver` step
+        #[serde(default)]
+        receiving_application: Option<String>,
+        #[serde(default)]
+        receiving_facility: Option<String>,
+    },
+    /// FHIR R4 server. Credentials hold a `token`, or a `client_id` and
+    /// `client_secret` exchanged at `token_url`.
+    Fhir {
+        base_url: String,
+        #[serde(default)]
+        token_url: Option<String>,
+    },
+    /// Directory batch files are dropped in, local when no host is given.
+    /// Credentials hold a `username` and `password` or `private_key`.
+    Sftp {
+        #[serde(default)]
+        host: Option<String>,
+        #[serde(default)]
+        port: Option<u16>,
+        path: String,
+        /// Where processed files are moved; `<path>/archive` by default
+        #[serde(default)]
+        archive_path: Option<String>,
+        /// Base64 SHA-256 of the server's host key. Connections to a server
+        /// presenting another key are refused.
+        #[serde(default)]
+        host_key_sha256: Option<String>,
+    },
+    /// ABDM gateway. Credentials hold the `client_id` and `client_secret`.
+    Abdm { gateway_url: String, cm_id: String },
+}
+
+impl ChannelEndpoint {
+    pub fn channel_type(&self) -> &'static str {
+        match self {
+            ChannelEndpoint::Mllp { .. } => "mllp",
+            ChannelEndpoint::Fhir { .. } => "fhir",
+            ChannelEndpoint::Sftp { .. } => "sftp",
+            ChannelEndpoint::Abdm { .. } => "abdm",
+        }
+    }
+
+    /// Protocol of the messages queued for the channel. Files from an SFTP
+    /// drop come in several formats, so it has none of its own.
+    pub fn protocol(&self) -> Option<Protocol> {
+        match self {
+            ChannelEndpoint::Mllp { .. } => Some(Protocol::Hl7v2),
+            ChannelEndpoint::Fhir { .. } => Some(Protocol::Fhir),
+            ChannelEndpoint::Sftp { .. } => None,
+            ChannelEndpoint::Abdm { .. } => Some(Protocol::Abdm),
+        }
+    }
+
+    /// Direction the channel carries messages in
+    pub fn direction(&self) -> Direction {
+        match self {
+            ChannelEndpoint::Sftp { .. } => Direction::Inbound,
+            _ => Direction::Outbound,
+        }
+    }
+}
+
+/// A configured channel as the API shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrationChannel {
+    pub id: Uuid,
+    pub name: String,
+    pub direction: Direction,
+    pub endpoint: ChannelEndpoint,
+    pub pipeline: Vec<TransformStep>,
+    /// Names of the stored credentials; their values are never returned
+    pub credential_keys: Vec<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelRequest {
+    pub name: String,
+    pub endpoint: ChannelEndpoint,
+    #[serde(default)]
+    pub pipeline: Vec<TransformStep>,
+    /// Replaces the stored credentials; left out, they are kept
+    #[serde(default)]
+    pub credentials: Option<Credentials>,
+    #[serde(default = "ChannelRequest::default_enabled")]
+    pub enabled: bool,
+}
+
+impl ChannelRequest {
+    fn default_enabled() -> bool {
+        true
+    }
+}
+
+/// A message to send through a channel
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelMessageRequest {
+    /// e.g. `ORU^R01`, or for ABDM the gateway API path
+    pub message_type: String,
+    pub payload: String,
+}
+
+/// A channel as the interface engine uses it, with its credentials opened
+#[derive(Debug, Clone)]
+pub struct LoadedChannel {
+    pub tenant_id: Uuid,
+    /// Admin who configured the channel, recorded as the actor of its work
+    pub created_by: Option<Uuid>,
+    pub channel: IntegrationChannel,
+    /// Why the credentials could not be opened, if they could not
+    pub credentials: Result<Credentials, String>,
+}
+
+/// Integration channel registry. Channels are kept in memory for the
+/// interface engine and reloaded whenever one changes in the database.
+pub struct IntegrationService {
+    pool: PgPool,
+    cipher: CredentialCipher,
+    channels: RwLock<HashMap<Uuid, Arc<LoadedChannel>>>,
+    transport: ChannelTransport,
+}
+
+impl IntegrationService {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_cipher(pool, CredentialCipher::from_env())
+    }
+
+    pub fn with_cipher(pool: PgPool, cipher: CredentialCipher) -> Self {
+        Self {
+            pool,
+            cipher,
+            channels: RwLock::new(HashMap::new()),
+            transport: ChannelTransport::default(),
+        }
+    }
+
+    /// Interface engine connection of a channel
+    pub fn connection(channel_id: Uuid) -> String {
+        format!("channel:{}", channel_id)
+    }
+
+    pub async fn create_channel(&self, actor: Uuid, request: ChannelRequest) -> Result<IntegrationChannel, HimsError> {
+        Self::validate(&request)?;
+        let (credentials, credential_keys) = self.seal(request.credentials.as_ref())?;
+
+        let row = sqlx::query(INSERT_CHANNEL)
+            .bind(Uuid::new_v4())
+            .bind(&request.name)
+            .bind(request.endpoint.channel_type())
+            .bind(request.endpoint.direction().as_str())
+            .bind(json!(request.endpoint))
+            .bind(Self::pipeline_names(&request.pipeline))
+            .bind(credentials)
+            .bind(credential_keys)
+            .bind(request.enabled)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Self::row_to_channel(&row)
+    }
+
+    pub async fn get_channel(&self, id: Uuid) -> Result<Option<IntegrationChannel>, HimsError> {
+        sqlx::query(GET_CHANNEL)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_channel)
+            .transpose()
+    }
+
+    pub async fn list_channels(&self) -> Result<Vec<IntegrationChannel>, HimsError> {
+        sqlx::query(LIST_CHANNELS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_channel)
+            .collect()
+    }
+
+    pub async fn update_channel(&self, id: Uuid, request: ChannelRequest) -> Result<Option<IntegrationChannel>, HimsError> {
+        Self::validate(&request)?;
+        let (credentials, credential_keys) = self.seal(request.credentials.as_ref())?;
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let Some(row) = sqlx::query(UPDATE_CHANNEL)
+            .bind(id)
+            .bind(&request.name)
+            .bind(request.endpoint.channel_type())
+            .bind(request.endpoint.direction().as_str())
+            .bind(json!(request.endpoint))
+            .bind(Self::pipeline_names(&request.pipeline))
+            .bind(credentials)
+            .bind(credential_keys)
+            .bind(request.credentials.is_some())
+            .bind(request.enabled)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db)?
+        else {
+            return Ok(None);
+        };
+        InterfaceEngineService::set_paused_by_name(&mut tx, &Self::connection(id), !request.enabled).await?;
+        tx.commit().await.map_err(db)?;
+        Self::row_to_channel(&row).map(Some)
+    }
+
+    /// Enable or disable a channel. Messages queued for a disabled channel
+    /// wait until it is enabled again.
+    pub async fn set_enabled(&self, id: Uuid, enabled: bool) -> Result<Option<IntegrationChannel>, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let Some(row) = sqlx::query(SET_CHANNEL_ENABLED)
+            .bind(id)
+            .bind(enabled)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db)?
+        else {
+            return Ok(None);
+        };
+        InterfaceEngineService::set_paused_by_name(&mut tx, &Self::connection(id), !enabled).await?;
+        tx.commit().await.map_err(db)?;
+        Self::row_to_channel(&row).map(Some)
+    }
+
+    /// Delete a channel along with its queued and sent messages
+    pub async fn delete_channel(&self, id: Uuid) -> Result<bool, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let result = sqlx::query(DELETE_CHANNEL).bind(id).execute(&mut *tx).await.map_err(db)?;
+        InterfaceEngineService::delete_connection_by_name(&mut tx, &Self::connection(id)).await?;
+        tx.commit().await.map_err(db)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Queue a message to send through an outbound channel
+    pub async fn enqueue(&self, channel_id: Uuid, request: ChannelMessageRequest) -> Result<Option<i64>, HimsError> {
+        let Some(channel) = self.get_channel(channel_id).await? else {
+            return Ok(None);
+        };
+        let protocol = match (channel.direction, channel.endpoint.protocol()) {
+            (Direction::Outbound, Some(protocol)) => protocol,
+            _ => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Channel {} does not send messages", channel.name),
+                })
+            }
+        };
+
+        let mut conn = self.pool.acquire().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let message = NewMessage {
+            connection: Self::connection(channel_id),
+            direction: Direction::Outbound,
+            protocol,
+            message_type: request.message_type,
+            payload: request.payload,
+            metadata: Value::Object(Default::default()),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        };
+        let id = InterfaceEngineService::enqueue_with(&mut conn, current_tenant_id(), &message).await?;
+        if !channel.enabled {
+            InterfaceEngineService::set_paused_by_name(&mut conn, &message.connection, true).await?;
+        }
+        Ok(Some(id))
+    }
+
+    pub fn validate(request: &ChannelRequest) -> Result<(), HimsError> {
+        let invalid = |message: String| Err(HimsError::ValidationError { message });
+        let is_url = |url: &str| url.starts_with("https://") || url.starts_with("http://");
+
+        if request.name.trim().is_empty() {
+            return invalid("Channel name is required".to_string());
+        }
+        match &request.endpoint {
+            ChannelEndpoint::Mllp { host, port, .. } if host.trim().is_empty() || *port == 0 => {
+                return invalid("MLLP channels need a host and port".to_string());
+            }
+            ChannelEndpoint::Fhir { base_url, token_url } if !is_url(base_url.as_str()) || !token_url.as_deref().is_none_or(is_url) => {
+                return invalid("FHIR channels need an http(s) base_url and token_url".to_string());
+            }
+            ChannelEndpoint::Sftp { path, .. } if path.trim().is_empty() => {
+                return invalid("SFTP channels need a path".to_string());
+            }
+            ChannelEndpoint::Abdm { gateway_url, cm_id } if !is_url(gateway_url.as_str()) || cm_id.trim().is_empty() => {
+                return invalid("ABDM channels need an http(s) gateway_url and a cm_id".to_string());
+            }
+            _ => {}
+        }
+
+        if let Some(protocol) = request.endpoint.protocol() {
+            if let Some(step) = request.pipeline.iter().find(|step| step.protocol() != protocol) {
+                return invalid(format!(
+                    "{} does not apply to {} channels",
+                    step.as_str(),
+                    request.endpoint.channel_type()
+                ));
+            }
+        }
+        if request.pipeline.contains(&TransformStep::Hl7RewriteReceiver)
+            && !matches!(request.endpoint, ChannelEndpoint::Mllp { .. })
+        {
+            return invalid("hl7-rewrite-receiver only applies to MLLP channels".to_string());
+        }
+
+        let needs: &[&str] = match &request.endpoint {
+            ChannelEndpoint::Fhir { token_url: Some(_), .. } | ChannelEndpoint::Abdm { .. } => &["client_id", "client_secret"],
+            ChannelEndpoint::Sftp { host: Some(_), .. } => &["username"],
+            _ => &[],
+        };
+        if let Some(credentials) = &request.credentials {
+            if let Some(missing) = needs.iter().find(|key| !credentials.contains_key(**key)) {
+                return invalid(format!("{} channels need a {} credential", request.endpoint.channel_type(), missing));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sealed credentials and their names, when credentials are given
+    fn seal(&self, credentials: Option<&Credentials>) -> Result<(Option<Vec<u8>>, Vec<String>), HimsError> {
+        match credentials {
+            Some(credentials) if !credentials.is_empty() => {
+                Ok((Some(self.cipher.seal(credentials)?), credentials.keys().cloned().collect()))
+            }
+            _ => Ok((None, Vec::new())),
+        }
+    }
+
+    fn pipeline_names(pipeline: &[TransformStep]) -> Vec<&'static str> {
+        pipeline.iter().map(TransformStep::as_str).collect()
+    }
+
+    /// The channel as last loaded, loading it if it is not known yet
+    pub async fn loaded(&self, id: Uuid) -> Result<Option<Arc<LoadedChannel>>, HimsError> {
+        let cached = self.channels.read().unwrap_or_else(|poisoned| poisoned.into_inner()).get(&id).cloned();
+        match cached {
+            Some(channel) => Ok(Some(channel)),
+            None => self.reload_channel(id).await,
+        }
+    }
+
+    /// Every channel as last loaded
+    pub fn loaded_channels(&self) -> Vec<Arc<LoadedChannel>> {
+        self.channels
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Reload one channel, dropping it if it no longer exists
+    pub async fn reload_channel(&self, id: Uuid) -> Result<Option<Arc<LoadedChannel>>, HimsError> {
+        let row = with_tenant(TenantContext::system(), sqlx::query(GET_CHANNEL).bind(id).fetch_optional(&self.pool))
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let loaded = row.as_ref().map(|row| self.load(row)).transpose()?.map(Arc::new);
+
+        let mut channels = self.channels.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match &loaded {
+            Some(channel) => channels.insert(id, channel.clone()),
+            None => channels.remove(&id),
+        };
+        Ok(loaded)
+    }
+
+    /// Reload every channel of every tenant. Returns how many there are.
+    pub async fn reload(&self) -> Result<usize, HimsError> {
+        let rows = with_tenant(TenantContext::system(), sqlx::query(LIST_CHANNELS).fetch_all(&self.pool))
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let loaded = rows
+            .iter()
+            .map(|row| self.load(row).map(|channel| (channel.channel.id, Arc::new(channel))))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        let count = loaded.len();
+        *self.channels.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = loaded;
+        Ok(count)
+    }
+
+    /// Keep the loaded channels current: reload one as soon as it changes,
+    /// and all of them every minute in case a change was missed
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            if let Err(e) = self.reload().await {
+                tracing::error!("Failed to load integration channels: {}", e);
+            }
+            let mut listener = match PgListener::connect_with(&self.pool).await {
+                Ok(mut listener) => match listener.listen(CHANGE_NOTIFICATIONS).await {
+                    Ok(()) => Some(listener),
+                    Err(e) => {
+                        tracing::error!("Failed to listen for channel changes: {}", e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("Failed to listen for channel changes: {}", e);
+                    None
+                }
+            };
+
+            let mut ticker = tokio::time::interval(FULL_RELOAD_INTERVAL);
+            ticker.tick().await;
+            loop {
+                let changed = match listener.as_mut() {
+                    Some(listener) => tokio::select! {
+                        notification = listener.recv() => Some(notification),
+                        _ = ticker.tick() => None,
+                    },
+                    None => {
+                        ticker.tick().await;
+                        None
+                    }
+                };
+
+                let result = match changed {
+                    Some(Ok(notification)) => match Uuid::parse_str(notification.payload()) {
+                        Ok(id) => self.reload_channel(id).await.map(|_| ()),
+                        Err(_) => self.reload().await.map(|_| ()),
+                    },
+                    // The listener reconnects by itself, but changes made
+                    // meanwhile were not announced
+                    Some(Err(e)) => {
+                        tracing::warn!("Channel change listener failed: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        self.reload().await.map(|_| ())
+                    }
+                    None => self.reload().await.map(|_| ()),
+                };
+                if let Err(e) = result {
+                    tracing::error!("Failed to reload integration channels: {}", e);
+                }
+            }
+        })
+    }
+
+    fn load(&self, row: &PgRow) -> Result<LoadedChannel, HimsError> {
+        let channel = Self::row_to_channel(row)?;
+        let sealed: Option<Vec<u8>> = row.try_get("credentials").map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let credentials = match sealed {
+            Some(sealed) => self.cipher.open(&sealed).map_err(|e| {
+                tracing::error!("Credentials of channel {} are unusable: {}", channel.name, e);
+                e.to_string()
+            }),
+            None => Ok(Credentials::new()),
+        };
+        Ok(LoadedChannel {
+            tenant_id: row.try_get("tenant_id").map_err(|e| HimsError::DatabaseError(e.to_string()))?,
+            created_by: row.try_get("created_by").map_err(|e| HimsError::DatabaseError(e.to_string()))?,
+            channel,
+            credentials,
+        })
+    }
+
+    fn row_to_channel(row: &PgRow) -> Result<IntegrationChannel, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let endpoint: Value = row.try_get("endpoint").map_err(read)?;
+        let direction: String = row.try_get("direction").map_err(read)?;
+        let pipeline: Vec<String> = row.try_get("pipeline").map_err(read)?;
+        Ok(IntegrationChannel {
+            id: row.try_get("id").map_err(read)?,
+            name: row.try_get("name").map_err(read)?,
+            direction: Direction::from_string(&direction)
+                .ok_or_else(|| HimsError::DatabaseError(format!("Unknown channel direction {}", direction)))?,
+            endpoint: serde_json::from_value(endpoint)
+                .map_err(|e| HimsError::DatabaseError(format!("Invalid channel endpoint: {}", e)))?,
+            pipeline: pipeline
+                .iter()
+                .map(|step| {
+                    TransformStep::from_string(step)
+                        .ok_or_else(|| HimsError::DatabaseError(format!("Unknown transformation step {}", step)))
+                })
+                .collect::<Result<_, _>>()?,
+            credential_keys: row.try_get("credential_keys").map_err(read)?,
+            enabled: row.try_get("enabled").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+            updated_at: row.try_get("updated_at").map_err(read)?,
+        })
+    }
+}
+
+#[async_trait]
+impl MessageHandler for IntegrationService {
+    /// Run a queued message through its channel's pipeline and send it with
+    /// the channel's current configuration
+    async fn handle(&self, message: &QueuedMessage) -> Result<Option<String>, HandlerError> {
+        let channel_id = message
+            .connection
+            .strip_prefix("channel:")
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(|| HandlerError::reject(format!("{} is not an integration channel", message.connection)))?;
+        let loaded = self
+            .loaded(channel_id)
+            .await
+            .map_err(|e| HandlerError::retry(e.to_string()))?
+            .ok_or_else(|| HandlerError::reject(format!("Integration channel {} no longer exists", channel_id)))?;
+        let channel = &loaded.channel;
+
+        if !channel.enabled {
+            return Err(HandlerError::retry(format!("Channel {} is disabled", channel.name)));
+        }
+        if channel.direction != Direction::Outbound {
+            return Err(HandlerError::reject(format!("Channel {} does not send messages", channel.name)));
+        }
+        let credentials = loaded.credentials.as_ref().map_err(|e| HandlerError::retry(e.clone()))?;
+
+        let payload = integration_pipeline::run(&channel.pipeline, &message.payload, &channel.endpoint)
+            .map_err(HandlerError::reject)?;
+        self.transport
+            .send(channel.id, &channel.endpoint, credentials, &message.message_type, &payload)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(endpoint: ChannelEndpoint) -> ChannelRequest {
+        ChannelRequest {
+            name: "Lab".to_string(),
+            endpoint,
+            pipeline: vec![],
+            credentials: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_endpoint_from_json() {
+        let endpoint: ChannelEndpoint =
+            serde_json::from_value(json!({ "type": "mllp", "host": "lis.local", "port": 2575 })).unwrap();
+        assert_eq!(endpoint.channel_type(), "mllp");
+        assert_eq!(endpoint.protocol(), Some(Protocol::Hl7v2));
+        assert_eq!(endpoint.direction(), Direction::Outbound);
+
+        let sftp: ChannelEndpoint = serde_json::from_value(json!({ "type": "sftp", "path": "/drop/results" })).unwrap();
+        assert_eq!(sftp.direction(), Direction::Inbound);
+        assert_eq!(sftp.protocol(), None);
+    }
+
+    #[test]
+    fn test_validate_channel() {
+        let fhir = ChannelEndpoint::Fhir {
+            base_url: "https://fhir.example.org/r4".to_string(),
+            token_url: Some("https://auth.example.org/token".to_string()),
+        };
+        let mut valid = request(fhir.clone());
+        valid.pipeline = vec![TransformStep::FhirMinify];
+        assert!(IntegrationService::validate(&valid).is_ok());
+
+        let mut wrong_step = request(fhir.clone());
+        wrong_step.pipeline = vec![TransformStep::Hl7StripZSegments];
+        assert!(IntegrationService::validate(&wrong_step).is_err());
+
+        let mut missing_secret = request(fhir);
+        missing_secret.credentials = Some(Credentials::from([("client_id".to_string(), "hims".to_string())]));
+        assert!(IntegrationService::validate(&missing_secret).is_err());
+
+        let no_port = request(ChannelEndpoint::Mllp {
+            host: "lis.local".to_string(),
+            port: 0,
+            receiving_application: None,
+            receiving_facility: None,
+        });
+        assert!(IntegrationService::validate(&no_port).is_err());
+    }
+
+    #[test]
+    fn test_credentials_sealed_at_rest() {
+        let cipher = CredentialCipher::new(Some([7u8; 32]));
+        let credentials = Credentials::from([("client_secret".to_string(), "s3cret".to_string())]);
+
+        let sealed = cipher.seal(&credentials).unwrap();
+        assert!(!sealed.windows(6).any(|window| window == b"s3cret"));
+        assert_eq!(cipher.open(&sealed).unwrap(), credentials);
+
+        assert!(CredentialCipher::new(Some([8u8; 32])).open(&sealed).is_err());
+        assert!(CredentialCipher::new(None).seal(&credentials).is_err());
+    }
+}
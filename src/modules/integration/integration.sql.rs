@@ -0,0 +1,86 @@
+//! Integration SQL Queries
+//!
+//! This file contains all SQL queries used by the integration service
+//! for clean separation of concerns and better maintainability.
+
+pub const INSERT_CHANNEL: &str = r#"
+    INSERT INTO integration_channels (
+        id, name, channel_type, direction, endpoint, pipeline, credentials, credential_keys, enabled, created_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    RETURNING id, tenant_id, name, direction, endpoint, pipeline, credentials, credential_keys, enabled, created_by, created_at, updated_at
+"#;
+
+pub const GET_CHANNEL: &str = r#"
+    SELECT id, tenant_id, name, direction, endpoint, pipeline, credentials, credential_keys, enabled, created_by, created_at, updated_at
+    FROM integration_channels
+    WHERE id = $1
+"#;
+
+pub const LIST_CHANNELS: &str = r#"
+    SELECT id, tenant_id, name, direction, endpoint, pipeline, credentials, credential_keys, enabled, created_by, created_at, updated_at
+    FROM integration_channels
+    ORDER BY name
+"#;
+
+/// Credentials are only replaced when new ones are given
+pub const UPDATE_CHANNEL: &str = r#"
+    UPDATE integration_channels
+    SET name = $2, channel_type = $3, direction = $4, endpoint = $5, pipeline = $6,
+        credentials = CASE WHEN $9 THEN $7 ELSE credentials END,
+        credential_keys = CASE WHEN $9 THEN $8 ELSE credential_keys END,
+        enabled = $10
+    WHERE id = $1
+    RETURNING id, tenant_id, name, direction, endpoint, pipeline, credentials, credential_keys, enabled, created_by, created_at, updated_at
+"#;
+
+pub const SET_CHANNEL_ENABLED: &str = r#"
+    UPDATE integration_channels SET enabled = $2
+    WHERE id = $1
+    RETURNING id, tenant_id, name, direction, endpoint, pipeline, credentials, credential_keys, enabled, created_by, created_at, updated_at
+"#;
+
+pub const DELETE_CHANNEL: &str = r#"
+    DELETE FROM integration_channels WHERE id = $1
+"#;
+
+/// Held until the transaction ends, so one server polls a drop at a time
+pub const TRY_LOCK_CHANNEL: &str = r#"
+    SELECT pg_try_advisory_xact_lock(hashtext('integration_channel:' || $1::text))
+"#;
+
+/// Active patients with an identifier of this value, whatever its system.
+/// At most two, to tell one match from several.
+pub const FIND_PATIENTS_BY_IDENTIFIER_VALUE: &str = r#"
+    SELECT id
+    FROM patients
+    WHERE active = true AND identifier @> jsonb_build_array(jsonb_build_object('value', $1::text))
+    ORDER BY id
+    LIMIT 2
+"#;
+
+/// Whether a file with these contents was already imported on the channel
+pub const FILE_ALREADY_IMPORTED: &str = r#"
+    SELECT EXISTS (
+        SELECT 1 FROM integration_file_imports
+        WHERE channel_id = $1 AND checksum = $2 AND status IN ('imported', 'partial')
+    )
+"#;
+
+pub const INSERT_FILE_IMPORT: &str = r#"
+    INSERT INTO integration_file_imports (
+        id, tenant_id, channel_id, file_name, format, checksum, size_bytes, status,
+        records_imported, records_failed, errors, archived_to
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+    RETURNING id, channel_id, file_name, format, checksum, size_bytes, status,
+        records_imported, records_failed, errors, archived_to, processed_at
+"#;
+
+/// Files of a channel, newest first, optionally by status ($2)
+pub const LIST_FILE_IMPORTS: &str = r#"
+    SELECT id, channel_id, file_name, format, checksum, size_bytes, status,
+        records_imported, records_failed, errors, archived_to, processed_at
+    FROM integration_file_imports
+    WHERE channel_id = $1 AND ($2::text IS NULL OR status = $2)
+    ORDER BY processed_at DESC
+    LIMIT $3 OFFSET $4
+"#;
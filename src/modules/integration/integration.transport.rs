@@ -0,0 +1,245 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::modules::integration::integration_credentials::Credentials;
+use crate::modules::integration::integration_service::ChannelEndpoint;
+use crate::modules::interface_engine::HandlerError;
+use crate::standards::hl7v2::mllp::MllpClient;
+
+/// Timeout of each HTTP request to a FHIR server or the ABDM gateway
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tokens are dropped this long before they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Sends a message to a channel's endpoint. Outcomes are interface engine
+/// handler results, so the engine knows whether to retry.
+pub struct ChannelTransport {
+    http: reqwest::Client,
+    mllp: MllpClient,
+    /// Access tokens per channel, with when they stop being used
+    tokens: Mutex<HashMap<Uuid, (String, Instant)>>,
+}
+
+impl Default for ChannelTransport {
+    fn default() -> Self {
+        Self {
+            http: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build().unwrap_or_default(),
+            mllp: MllpClient::default(),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ChannelTransport {
+    pub async fn send(
+        &self,
+        channel_id: Uuid,
+        endpoint: &ChannelEndpoint,
+        credentials: &Credentials,
+        message_type: &str,
+        payload: &str,
+    ) -> Result<Option<String>, HandlerError> {
+        match endpoint {
+            ChannelEndpoint::Mllp { host, port, .. } => self.send_mllp(host, *port, payload).await,
+            ChannelEndpoint::Fhir { base_url, token_url } => {
+                let resource: Value =
+                    serde_json::from_str(payload).map_err(|e| HandlerError::reject(format!("Payload is not JSON: {}", e)))?;
+                let url = match resource["resourceType"].as_str() {
+                    Some("Bundle") => base_url.trim_end_matches('/').to_string(),
+                    Some(resource_type) => format!("{}/{}", base_url.trim_end_matches('/'), resource_type),
+                    None => return Err(HandlerError::reject("Payload has no resourceType")),
+                };
+                let token = match (credentials.get("token"), token_url) {
+                    (Some(token), _) => Some(token.clone()),
+                    (None, Some(token_url)) => Some(self.client_credentials_token(channel_id, token_url, credentials).await?),
+                    (None, None) => None,
+                };
+
+                let mut request = self
+                    .http
+                    .post(url)
+                    .header("Content-Type", "application/fhir+json")
+                    .body(payload.to_string());
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                self.outcome(channel_id, request.send().await).await
+            }
+            ChannelEndpoint::Abdm { gateway_url, cm_id } => {
+                let token = self.abdm_session(channel_id, gateway_url, credentials).await?;
+                let path = if message_type.starts_with('/') {
+                    message_type.to_string()
+                } else {
+                    format!("/{}", message_type)
+                };
+                let request = self
+                    .http
+                    .post(format!("{}{}", gateway_url.trim_end_matches('/'), path))
+                    .bearer_auth(token)
+                    .header("X-CM-ID", cm_id)
+                    .header("Content-Type", "application/json")
+                    .body(payload.to_string());
+                self.outcome(channel_id, request.send().await).await
+            }
+            ChannelEndpoint::Sftp { .. } => Err(HandlerError::reject("SFTP channels only receive files")),
+        }
+    }
+
+    /// Send over MLLP. Accepted only when the acknowledgement names the
+    /// message's control ID (MSH-10).
+    async fn send_mllp(&self, host: &str, port: u16, payload: &str) -> Result<Option<String>, HandlerError> {
+        let control_id = payload
+            .split(['\r', '\n'])
+            .find(|segment| segment.starts_with("MSH|"))
+            .and_then(|msh| msh.split('|').nth(9))
+            .unwrap_or_default()
+            .to_string();
+
+        let ack = self
+            .mllp
+            .send(host, port, payload)
+            .await
+            .map_err(|e| HandlerError::retry(e.to_string()))?;
+        if ack.is_accepted() && ack.control_id == control_id {
+            Ok(Some(ack.code))
+        } else if ack.is_accepted() {
+            Err(HandlerError::retry(format!("Acknowledgement names control ID {}", ack.control_id)).with_response(ack.code))
+        } else {
+            let error = ack.text.clone().unwrap_or_else(|| format!("Receiver answered {}", ack.code));
+            let error = match ack.code.as_str() {
+                "AR" | "CR" => HandlerError::reject(error),
+                _ => HandlerError::retry(error),
+            };
+            Err(error.with_response(ack.code))
+        }
+    }
+
+    /// Outcome of an HTTP delivery. Client errors other than timeouts and
+    /// throttling will not change on resending.
+    async fn outcome(
+        &self,
+        channel_id: Uuid,
+        response: Result<reqwest::Response, reqwest::Error>,
+    ) -> Result<Option<String>, HandlerError> {
+        let response = response.map_err(|e| HandlerError::retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(Some(status.as_u16().to_string()));
+        }
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            self.tokens.lock().await.remove(&channel_id);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let error = format!("Endpoint answered {}", status);
+        let error = match status.as_u16() {
+            401 | 408 | 429 => HandlerError::retry(error),
+            400..=499 => HandlerError::reject(error),
+            _ => HandlerError::retry(error),
+        };
+        Err(error.with_response(body))
+    }
+
+    async fn cached_token(&self, channel_id: Uuid) -> Option<String> {
+        self.tokens
+            .lock()
+            .await
+            .get(&channel_id)
+            .filter(|(_, until)| *until > Instant::now())
+            .map(|(token, _)| token.clone())
+    }
+
+    async fn cache_token(&self, channel_id: Uuid, token: &str, expires_in: Option<u64>) {
+        let lifetime = Duration::from_secs(expires_in.unwrap_or(300)).saturating_sub(TOKEN_EXPIRY_MARGIN);
+        self.tokens
+            .lock()
+            .await
+            .insert(channel_id, (token.to_string(), Instant::now() + lifetime));
+    }
+
+    fn credential<'a>(credentials: &'a Credentials, key: &str) -> Result<&'a str, HandlerError> {
+        credentials
+            .get(key)
+            .map(String::as_str)
+            .ok_or_else(|| HandlerError::reject(format!("Channel has no {} credential", key)))
+    }
+
+    /// OAuth 2.0 client credentials grant, as SMART backend services use
+    async fn client_credentials_token(
+        &self,
+        channel_id: Uuid,
+        token_url: &str,
+        credentials: &Credentials,
+    ) -> Result<String, HandlerError> {
+        if let Some(token) = self.cached_token(channel_id).await {
+            return Ok(token);
+        }
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", Self::credential(credentials, "client_id")?),
+            ("client_secret", Self::credential(credentials, "client_secret")?),
+        ];
+        if let Some(scope) = credentials.get("scope") {
+            form.push(("scope", scope));
+        }
+
+        let response = self
+            .http
+            .post(token_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| HandlerError::retry(format!("Token request failed: {}", e)))?;
+        let token = Self::token_response(response, "access_token", "expires_in").await?;
+        self.cache_token(channel_id, &token.0, token.1).await;
+        Ok(token.0)
+    }
+
+    /// ABDM gateway session token
+    async fn abdm_session(&self, channel_id: Uuid, gateway_url: &str, credentials: &Credentials) -> Result<String, HandlerError> {
+        if let Some(token) = self.cached_token(channel_id).await {
+            return Ok(token);
+        }
+        let response = self
+            .http
+            .post(format!("{}/v0.5/sessions", gateway_url.trim_end_matches('/')))
+            .json(&json!({
+                "clientId": Self::credential(credentials, "client_id")?,
+                "clientSecret": Self::credential(credentials, "client_secret")?,
+            }))
+            .send()
+            .await
+            .map_err(|e| HandlerError::retry(format!("Gateway session request failed: {}", e)))?;
+        let token = Self::token_response(response, "accessToken", "expiresIn").await?;
+        self.cache_token(channel_id, &token.0, token.1).await;
+        Ok(token.0)
+    }
+
+    async fn token_response(
+        response: reqwest::Response,
+        token_field: &str,
+        expiry_field: &str,
+    ) -> Result<(String, Option<u64>), HandlerError> {
+        let status = response.status();
+        if !status.is_success() {
+            let error = format!("Token endpoint answered {}", status);
+            // Bad client credentials need an admin to fix them
+            return Err(match status.as_u16() {
+                400 | 401 | 403 => HandlerError::reject(error),
+                _ => HandlerError::retry(error),
+            });
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| HandlerError::retry(format!("Invalid token response: {}", e)))?;
+        let token = body[token_field]
+            .as_str()
+            .ok_or_else(|| HandlerError::retry(format!("Token response has no {}", token_field)))?;
+        Ok((token.to_string(), body[expiry_field].as_u64()))
+    }
+}
@@ -0,0 +1,75 @@
+//! Integration Module
+//!
+//! This module provides the registry of integration channels including:
+//! - MLLP, FHIR server, SFTP drop and ABDM gateway channels configured per tenant
+//! - Credentials sealed at rest with AES-256-GCM; only their names are ever returned
+//! - A transformation pipeline per channel applied to each message it sends
+//! - Enable/disable toggles, with changes hot-reloaded by the interface engine
+//! - A watcher that imports HL7 batch, CSV and CCD files from SFTP and local drops
+
+#[path = "integration.controller.rs"]
+pub mod integration_controller;
+#[path = "integration.credentials.rs"]
+pub mod integration_credentials;
+#[path = "integration.filedrop.rs"]
+pub mod integration_filedrop;
+#[path = "integration.files.rs"]
+pub mod integration_files;
+#[path = "integration.pipeline.rs"]
+pub mod integration_pipeline;
+#[path = "integration.service.rs"]
+pub mod integration_service;
+#[path = "integration.sql.rs"]
+pub mod integration_sql;
+#[path = "integration.transport.rs"]
+pub mod integration_transport;
+
+pub use integration_controller::IntegrationController;
+pub use integration_credentials::{CredentialCipher, Credentials};
+pub use integration_filedrop::{FileDropService, FileImport, FileImportStatus};
+pub use integration_files::FileFormat;
+pub use integration_pipeline::TransformStep;
+pub use integration_service::{ChannelEndpoint, ChannelRequest, IntegrationChannel, IntegrationService, LoadedChannel};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::events::EventBus;
+use crate::utils::api_router::ApiRouter;
+
+/// Integration Module Configuration
+pub struct IntegrationModule {
+    pub service: Arc<IntegrationService>,
+    pub file_drop: Arc<FileDropService>,
+    pub controller: Arc<IntegrationController>,
+}
+
+impl IntegrationModule {
+    /// Create a new Integration Module with dependency injection
+    pub fn new(db_pool: PgPool, events: EventBus) -> Self {
+        let service = Arc::new(IntegrationService::new(db_pool.clone()));
+        let file_drop = Arc::new(FileDropService::new(db_pool, service.clone(), events));
+        let controller = Arc::new(IntegrationController::new(service.clone(), file_drop.clone()));
+
+        Self {
+            service,
+            file_drop,
+            controller,
+        }
+    }
+
+    /// Register integration routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<IntegrationService> {
+        self.service.clone()
+    }
+
+    /// Get the file drop watcher
+    pub fn get_file_drop(&self) -> Arc<FileDropService> {
+        self.file_drop.clone()
+    }
+}
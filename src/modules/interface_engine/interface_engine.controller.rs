@@ -0,0 +1,255 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::interface_engine::interface_engine_service::{
+    ConnectionSummary, Direction, MessageStatus, Protocol, QueuedMessage,
+};
+use crate::modules::interface_engine::InterfaceEngineService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Query for the message log
+#[derive(Debug, Deserialize)]
+pub struct MessageQuery {
+    pub connection: Option<Uuid>,
+    pub status: Option<MessageStatus>,
+    pub protocol: Option<Protocol>,
+    pub direction: Option<Direction>,
+    #[serde(rename = "_count")]
+    pub count: Option<i64>,
+    #[serde(rename = "_offset")]
+    pub offset: Option<i64>,
+}
+
+/// Interface engine controller for inspecting queues and replaying dead
+/// letters
+pub struct InterfaceEngineController {
+    interface_engine_service: Arc<InterfaceEngineService>,
+}
+
+impl InterfaceEngineController {
+    /// Create new controller with injected service
+    pub fn new(interface_engine_service: Arc<InterfaceEngineService>) -> Self {
+        Self { interface_engine_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/connections", Self::list_connections, "List interface connections")
+            .get("/connections/:id", Self::get_connection, "Get interface connection by ID")
+            .post("/connections/:id/pause", Self::pause_connection, "Pause an interface connection")
+            .post("/connections/:id/resume", Self::resume_connection, "Resume an interface connection")
+            .post("/connections/:id/replay", Self::replay_connection, "Replay a connection's dead letters")
+            .get("/messages", Self::list_messages, "List interface messages")
+            .get("/dead-letters", Self::list_dead_letters, "List dead-lettered interface messages")
+            .get("/messages/:id", Self::get_message, "Get interface message by ID")
+            .post("/messages/:id/replay", Self::replay_message, "Replay a dead-lettered interface message")
+            .with_state(self.interface_engine_service.clone())
+    }
+
+    /// List connections with their queue depths
+    pub async fn list_connections(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<ConnectionSummary>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match interface_engine_service.list_connections().await {
+            Ok(connections) => Ok(Json(connections)),
+            Err(e) => Err(Self::error_response("Failed to list interface connections", e)),
+        }
+    }
+
+    /// Get connection by ID
+    pub async fn get_connection(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<ConnectionSummary>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match interface_engine_service.get_connection(id).await {
+            Ok(Some(connection)) => Ok(Json(connection)),
+            Ok(None) => Err(Self::not_found("Interface connection", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve interface connection", e)),
+        }
+    }
+
+    /// Stop handling a connection's messages; they keep queueing
+    pub async fn pause_connection(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Pausing interface connection: {}", id);
+
+        match interface_engine_service.set_paused(id, true).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found("Interface connection", id)),
+            Err(e) => Err(Self::error_response("Failed to pause interface connection", e)),
+        }
+    }
+
+    /// Resume handling a connection's messages
+    pub async fn resume_connection(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Resuming interface connection: {}", id);
+
+        match interface_engine_service.set_paused(id, false).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found("Interface connection", id)),
+            Err(e) => Err(Self::error_response("Failed to resume interface connection", e)),
+        }
+    }
+
+    /// Replay every dead letter of a connection
+    pub async fn replay_connection(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} replaying dead letters of interface connection {}", actor, id);
+
+        match interface_engine_service.replay_connection(id).await {
+            Ok(replayed) => Ok((StatusCode::ACCEPTED, Json(json!({ "replayed": replayed })))),
+            Err(e) => Err(Self::error_response("Failed to replay interface connection", e)),
+        }
+    }
+
+    /// List messages, newest first
+    pub async fn list_messages(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Query(query): Query<MessageQuery>,
+    ) -> Result<Json<Vec<QueuedMessage>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match interface_engine_service
+            .list_messages(query.connection, query.status, query.protocol, query.direction, query.count, query.offset)
+            .await
+        {
+            Ok(messages) => Ok(Json(messages)),
+            Err(e) => Err(Self::error_response("Failed to list interface messages", e)),
+        }
+    }
+
+    /// List dead letters, newest first
+    pub async fn list_dead_letters(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Query(query): Query<MessageQuery>,
+    ) -> Result<Json<Vec<QueuedMessage>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match interface_engine_service
+            .list_messages(
+                query.connection,
+                Some(MessageStatus::Dead),
+                query.protocol,
+                query.direction,
+                query.count,
+                query.offset,
+            )
+            .await
+        {
+            Ok(messages) => Ok(Json(messages)),
+            Err(e) => Err(Self::error_response("Failed to list dead letters", e)),
+        }
+    }
+
+    /// Get message by ID
+    pub async fn get_message(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Path(id): Path<i64>,
+    ) -> Result<Json<QueuedMessage>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match interface_engine_service.get_message(id).await {
+            Ok(Some(message)) => Ok(Json(message)),
+            Ok(None) => Err(Self::not_found("Interface message", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve interface message", e)),
+        }
+    }
+
+    /// Replay a dead letter
+    pub async fn replay_message(
+        State(interface_engine_service): State<Arc<InterfaceEngineService>>,
+        headers: HeaderMap,
+        Path(id): Path<i64>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} replaying interface message {}", actor, id);
+
+        match interface_engine_service.replay_message(id).await {
+            Ok(true) => Ok(StatusCode::ACCEPTED),
+            Ok(false) => Err(Self::not_found("Dead-lettered interface message", id)),
+            Err(e) => Err(Self::error_response("Failed to replay interface message", e)),
+        }
+    }
+
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(what: &str, id: impl std::fmt::Display) -> (StatusCode, Json<ErrorResponse>) {
+        tracing::warn!("{} not found: {}", what, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", what),
+                message: format!("{} with id {} not found", what, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        if status == StatusCode::FORBIDDEN {
+            tracing::warn!("{}: {}", context, e);
+        } else {
+            tracing::error!("{}: {}", context, e);
+        }
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
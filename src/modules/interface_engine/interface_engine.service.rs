@@ -0,0 +1,582 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::modules::metrics::metrics_service::QueueDepth;
+
+// Import SQL queries from separate file
+use crate::modules::interface_engine::interface_engine_sql::*;
+
+/// Connections handled per engine pass
+const HANDLE_BATCH: i64 = 100;
+
+/// Attempts before a message becomes a dead letter, unless it says otherwise
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Received from another system, to be processed here
+    Inbound,
+    /// Produced here, to be sent to another system
+    Outbound,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Inbound => "inbound",
+            Direction::Outbound => "outbound",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "inbound" => Some(Direction::Inbound),
+            "outbound" => Some(Direction::Outbound),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Hl7v2,
+    Fhir,
+    Abdm,
+}
+
+impl Protocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Hl7v2 => "hl7v2",
+            Protocol::Fhir => "fhir",
+            Protocol::Abdm => "abdm",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "hl7v2" => Some(Protocol::Hl7v2),
+            "fhir" => Some(Protocol::Fhir),
+            "abdm" => Some(Protocol::Abdm),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageStatus {
+    Pending,
+    Delivered,
+    /// Out of attempts or rejected; kept for inspection and replay
+    Dead,
+}
+
+impl MessageStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageStatus::Pending => "pending",
+            MessageStatus::Delivered => "delivered",
+            MessageStatus::Dead => "dead",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "delivered" => MessageStatus::Delivered,
+            "dead" => MessageStatus::Dead,
+            _ => MessageStatus::Pending,
+        }
+    }
+}
+
+/// A message to queue
+#[derive(Debug, Clone)]
+pub struct NewMessage {
+    /// Ordering key; messages with the same connection are handled one at
+    /// a time in the order queued. Its scheme picks the handler, e.g.
+    /// `hl7:<destination id>`.
+    pub connection: String,
+    pub direction: Direction,
+    pub protocol: Protocol,
+    /// e.g. `ADT^A04`
+    pub message_type: String,
+    pub payload: String,
+    pub metadata: Value,
+    pub max_attempts: i32,
+}
+
+/// A message in the queue
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedMessage {
+    pub id: i64,
+    pub tenant_id: Uuid,
+    pub connection_id: Uuid,
+    pub connection: String,
+    pub direction: Direction,
+    pub protocol: Protocol,
+    pub message_type: String,
+    pub payload: String,
+    pub metadata: Value,
+    pub status: MessageStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub response: Option<String>,
+    pub replays: i32,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub dead_at: Option<DateTime<Utc>>,
+}
+
+/// A connection and how its queue stands
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub direction: Direction,
+    pub protocol: Protocol,
+    pub paused: bool,
+    pub pending: i64,
+    pub delivered: i64,
+    pub dead: i64,
+    pub oldest_pending_at: Option<DateTime<Utc>>,
+    pub last_delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Why a handler could not handle a message
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandlerError {
+    pub message: String,
+    /// The other side's answer, when there was one
+    pub response: Option<String>,
+    /// Retrying cannot help, so the message goes straight to the dead letters
+    pub permanent: bool,
+}
+
+impl HandlerError {
+    /// A failure that may clear up, such as the receiver being unreachable
+    pub fn retry(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            response: None,
+            permanent: false,
+        }
+    }
+
+    /// A failure that resending will not fix, such as a rejected message
+    pub fn reject(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            response: None,
+            permanent: true,
+        }
+    }
+
+    pub fn with_response(mut self, response: impl Into<String>) -> Self {
+        self.response = Some(response.into());
+        self
+    }
+}
+
+/// Sends or processes the messages of the connections it is registered for
+#[async_trait]
+pub trait MessageHandler: Send + Sync {
+    /// Handle a message, returning the other side's answer if any. Called
+    /// under a system tenant; the message carries its own `tenant_id`.
+    async fn handle(&self, message: &QueuedMessage) -> Result<Option<String>, HandlerError>;
+}
+
+/// Part of a connection name before the first `:`, which picks its handler
+pub fn scheme(connection: &str) -> &str {
+    connection.split_once(':').map_or(connection, |(scheme, _)| scheme)
+}
+
+/// Wait before retry `attempt` (1-based): 10s doubling, capped at 30 minutes
+pub fn retry_delay(attempt: i32) -> Duration {
+    let seconds = 10u64.saturating_mul(1 << attempt.clamp(1, 16).saturating_sub(1));
+    Duration::from_secs(seconds.min(1800))
+}
+
+/// When to try a failed message again, or `None` if it becomes a dead
+/// letter. `attempts` excludes the one that just failed.
+pub fn next_attempt(error: &HandlerError, attempts: i32, max_attempts: i32) -> Option<Duration> {
+    if error.permanent || attempts + 1 >= max_attempts {
+        None
+    } else {
+        Some(retry_delay(attempts + 1))
+    }
+}
+
+/// Durable queue for interface traffic, handed to registered handlers in
+/// order per connection with retries and dead letters
+pub struct InterfaceEngineService {
+    pool: PgPool,
+    handlers: RwLock<HashMap<String, Arc<dyn MessageHandler>>>,
+}
+
+impl InterfaceEngineService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            handlers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Handle messages of connections named `<scheme>:...` with `handler`,
+    /// replacing any handler registered before
+    pub fn register(&self, scheme: &str, handler: Arc<dyn MessageHandler>) {
+        self.handlers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(scheme.to_string(), handler);
+    }
+
+    fn handler(&self, connection: &str) -> Option<Arc<dyn MessageHandler>> {
+        self.handlers
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(scheme(connection))
+            .cloned()
+    }
+
+    /// Queue a message for a tenant
+    pub async fn enqueue(&self, tenant_id: Uuid, message: &NewMessage) -> Result<i64, HimsError> {
+        let mut conn = self.pool.acquire().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Self::enqueue_with(&mut conn, tenant_id, message).await
+    }
+
+    /// Queue a message as part of the caller's transaction, so it is only
+    /// sent if the change it reports is committed
+    pub async fn enqueue_with(conn: &mut PgConnection, tenant_id: Uuid, message: &NewMessage) -> Result<i64, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let connection_id: Uuid = sqlx::query_scalar(UPSERT_CONNECTION)
+            .bind(tenant_id)
+            .bind(&message.connection)
+            .bind(message.direction.as_str())
+            .bind(message.protocol.as_str())
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(db)?;
+        sqlx::query_scalar(INSERT_MESSAGE)
+            .bind(tenant_id)
+            .bind(connection_id)
+            .bind(&message.message_type)
+            .bind(&message.payload)
+            .bind(&message.metadata)
+            .bind(message.max_attempts.max(1))
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(db)
+    }
+
+    /// Pause or resume the current tenant's connection by name, e.g. when
+    /// the system it talks to is switched off
+    pub async fn set_paused_by_name(conn: &mut PgConnection, name: &str, paused: bool) -> Result<(), HimsError> {
+        sqlx::query(SET_CONNECTION_PAUSED_BY_NAME)
+            .bind(name)
+            .bind(paused)
+            .execute(conn)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Delete the current tenant's connection by name with all its messages
+    pub async fn delete_connection_by_name(conn: &mut PgConnection, name: &str) -> Result<(), HimsError> {
+        sqlx::query(DELETE_CONNECTION_BY_NAME)
+            .bind(name)
+            .execute(conn)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn list_connections(&self) -> Result<Vec<ConnectionSummary>, HimsError> {
+        sqlx::query(LIST_CONNECTIONS)
+            .bind(None::<Uuid>)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_connection)
+            .collect()
+    }
+
+    pub async fn get_connection(&self, id: Uuid) -> Result<Option<ConnectionSummary>, HimsError> {
+        sqlx::query(LIST_CONNECTIONS)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_connection)
+            .transpose()
+    }
+
+    pub async fn set_paused(&self, id: Uuid, paused: bool) -> Result<bool, HimsError> {
+        let result = sqlx::query(SET_CONNECTION_PAUSED)
+            .bind(id)
+            .bind(paused)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_message(&self, id: i64) -> Result<Option<QueuedMessage>, HimsError> {
+        sqlx::query(GET_MESSAGE)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_message)
+            .transpose()
+    }
+
+    /// Messages newest first, optionally only of one connection, status,
+    /// protocol or direction
+    pub async fn list_messages(
+        &self,
+        connection_id: Option<Uuid>,
+        status: Option<MessageStatus>,
+        protocol: Option<Protocol>,
+        direction: Option<Direction>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<QueuedMessage>, HimsError> {
+        sqlx::query(LIST_MESSAGES)
+            .bind(connection_id)
+            .bind(status.map(|status| status.as_str()))
+            .bind(protocol.map(|protocol| protocol.as_str()))
+            .bind(direction.map(|direction| direction.as_str()))
+            .bind(limit.unwrap_or(50).clamp(1, 500))
+            .bind(offset.unwrap_or(0).max(0))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_message)
+            .collect()
+    }
+
+    /// Queue a dead letter again with fresh attempts. Being older than
+    /// anything pending on its connection, it is handled next.
+    pub async fn replay_message(&self, id: i64) -> Result<bool, HimsError> {
+        let result = sqlx::query(REPLAY_MESSAGE)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Queue every dead letter of a connection again, in their original
+    /// order. Returns how many were replayed.
+    pub async fn replay_connection(&self, connection_id: Uuid) -> Result<u64, HimsError> {
+        let result = sqlx::query(REPLAY_CONNECTION)
+            .bind(connection_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(result.rows_affected())
+    }
+
+    /// Hand the next due message of each connection to its handler.
+    /// Returns how many were delivered.
+    pub async fn process_once(&self) -> Result<usize, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let due = sqlx::query(GET_NEXT_MESSAGES)
+            .bind(HANDLE_BATCH)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(db)?
+            .iter()
+            .map(Self::row_to_message)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut delivered = 0;
+        for message in due {
+            let result = match self.handler(&message.connection) {
+                Some(handler) => handler.handle(&message).await,
+                None => Err(HandlerError::retry(format!("No handler for {} connections", scheme(&message.connection)))),
+            };
+
+            match result {
+                Ok(response) => {
+                    sqlx::query(MARK_DELIVERED).bind(message.id).bind(response).execute(&mut *tx).await.map_err(db)?;
+                    delivered += 1;
+                }
+                Err(error) => match next_attempt(&error, message.attempts, message.max_attempts) {
+                    Some(delay) => {
+                        tracing::debug!("Interface message {} on {} failed: {}", message.id, message.connection, error.message);
+                        sqlx::query(RETRY_MESSAGE)
+                            .bind(message.id)
+                            .bind(&error.message)
+                            .bind(&error.response)
+                            .bind(delay.as_secs_f64())
+                            .execute(&mut *tx)
+                            .await
+                            .map_err(db)?;
+                    }
+                    None => {
+                        tracing::warn!(
+                            "Interface message {} on {} dead-lettered after {} attempts: {}",
+                            message.id,
+                            message.connection,
+                            message.attempts + 1,
+                            error.message
+                        );
+                        sqlx::query(DEAD_LETTER_MESSAGE)
+                            .bind(message.id)
+                            .bind(&error.message)
+                            .bind(&error.response)
+                            .execute(&mut *tx)
+                            .await
+                            .map_err(db)?;
+                    }
+                },
+            }
+        }
+
+        tx.commit().await.map_err(db)?;
+        Ok(delivered)
+    }
+
+    /// Process the queue every `interval`, across tenants
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.process_once().await {
+                    tracing::error!("Interface engine pass failed: {}", e);
+                }
+            }
+        }))
+    }
+
+    fn row_to_connection(row: &PgRow) -> Result<ConnectionSummary, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        Ok(ConnectionSummary {
+            id: row.try_get("id").map_err(read)?,
+            name: row.try_get("name").map_err(read)?,
+            direction: Self::direction(row)?,
+            protocol: Self::protocol(row)?,
+            paused: row.try_get("paused").map_err(read)?,
+            pending: row.try_get("pending").map_err(read)?,
+            delivered: row.try_get("delivered").map_err(read)?,
+            dead: row.try_get("dead").map_err(read)?,
+            oldest_pending_at: row.try_get("oldest_pending_at").map_err(read)?,
+            last_delivered_at: row.try_get("last_delivered_at").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+        })
+    }
+
+    fn row_to_message(row: &PgRow) -> Result<QueuedMessage, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        Ok(QueuedMessage {
+            id: row.try_get("id").map_err(read)?,
+            tenant_id: row.try_get("tenant_id").map_err(read)?,
+            connection_id: row.try_get("connection_id").map_err(read)?,
+            connection: row.try_get("connection").map_err(read)?,
+            direction: Self::direction(row)?,
+            protocol: Self::protocol(row)?,
+            message_type: row.try_get("message_type").map_err(read)?,
+            payload: row.try_get("payload").map_err(read)?,
+            metadata: row.try_get("metadata").map_err(read)?,
+            status: MessageStatus::from_string(&row.try_get::<String, _>("status").map_err(read)?),
+            attempts: row.try_get("attempts").map_err(read)?,
+            max_attempts: row.try_get("max_attempts").map_err(read)?,
+            next_attempt_at: row.try_get("next_attempt_at").map_err(read)?,
+            last_error: row.try_get("last_error").map_err(read)?,
+            response: row.try_get("response").map_err(read)?,
+            replays: row.try_get("replays").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+            delivered_at: row.try_get("delivered_at").map_err(read)?,
+            dead_at: row.try_get("dead_at").map_err(read)?,
+        })
+    }
+
+    fn direction(row: &PgRow) -> Result<Direction, HimsError> {
+        let direction: String = row.try_get("direction").map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Direction::from_string(&direction)
+            .ok_or_else(|| HimsError::DatabaseError(format!("Unknown interface direction {}", direction)))
+    }
+
+    fn protocol(row: &PgRow) -> Result<Protocol, HimsError> {
+        let protocol: String = row.try_get("protocol").map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Protocol::from_string(&protocol)
+            .ok_or_else(|| HimsError::DatabaseError(format!("Unknown interface protocol {}", protocol)))
+    }
+}
+
+#[async_trait]
+impl QueueDepth for InterfaceEngineService {
+    /// Messages waiting for their handler, including ones backing off
+    async fn depth(&self) -> Result<i64, HimsError> {
+        sqlx::query_scalar(COUNT_PENDING_MESSAGES)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failures_retry_until_dead_letter() {
+        let failed = HandlerError::retry("Connection refused");
+        assert_eq!(next_attempt(&failed, 0, 3), Some(Duration::from_secs(10)));
+        assert_eq!(next_attempt(&failed, 1, 3), Some(Duration::from_secs(20)));
+        assert_eq!(next_attempt(&failed, 2, 3), None);
+
+        let rejected = HandlerError::reject("Unknown patient").with_response("AR");
+        assert_eq!(next_attempt(&rejected, 0, 3), None);
+        assert_eq!(rejected.response.as_deref(), Some("AR"));
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        assert_eq!(retry_delay(1), Duration::from_secs(10));
+        assert_eq!(retry_delay(3), Duration::from_secs(40));
+        assert_eq!(retry_delay(DEFAULT_MAX_ATTEMPTS), Duration::from_secs(1800));
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for direction in [Direction::Inbound, Direction::Outbound] {
+            assert_eq!(Direction::from_string(direction.as_str()), Some(direction));
+        }
+        for protocol in [Protocol::Hl7v2, Protocol::Fhir, Protocol::Abdm] {
+            assert_eq!(Protocol::from_string(protocol.as_str()), Some(protocol));
+        }
+        assert_eq!(Protocol::from_string("x12"), None);
+    }
+
+    #[test]
+    fn test_connection_scheme_picks_handler() {
+        assert_eq!(scheme("hl7:7d0c5a52-5b8e-4d1e-9a7c-1f0a7f1c2b3d"), "hl7");
+        assert_eq!(scheme("channel:lab:primary"), "channel");
+        assert_eq!(scheme("standalone"), "standalone");
+    }
+}
@@ -0,0 +1,130 @@
+//! Interface Engine SQL Queries
+//!
+//! This file contains all SQL queries used by the interface engine service
+//! for clean separation of concerns and better maintainability.
+
+/// Find or create a tenant's connection by name
+pub const UPSERT_CONNECTION: &str = r#"
+    INSERT INTO interface_connections (tenant_id, name, direction, protocol)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT (tenant_id, name) DO UPDATE SET name = EXCLUDED.name
+    RETURNING id
+"#;
+
+pub const INSERT_MESSAGE: &str = r#"
+    INSERT INTO interface_messages (tenant_id, connection_id, message_type, payload, metadata, max_attempts)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    RETURNING id
+"#;
+
+/// Connections with message counts, busiest first
+pub const LIST_CONNECTIONS: &str = r#"
+    SELECT c.id, c.name, c.direction, c.protocol, c.paused, c.created_at,
+        COUNT(m.id) FILTER (WHERE m.status = 'pending') AS pending,
+        COUNT(m.id) FILTER (WHERE m.status = 'delivered') AS delivered,
+        COUNT(m.id) FILTER (WHERE m.status = 'dead') AS dead,
+        MIN(m.created_at) FILTER (WHERE m.status = 'pending') AS oldest_pending_at,
+        MAX(m.delivered_at) AS last_delivered_at
+    FROM interface_connections c
+    LEFT JOIN interface_messages m ON m.connection_id = c.id
+    WHERE ($1::uuid IS NULL OR c.id = $1)
+    GROUP BY c.id
+    ORDER BY COUNT(m.id) FILTER (WHERE m.status = 'pending') DESC, c.name
+"#;
+
+pub const SET_CONNECTION_PAUSED: &str = r#"
+    UPDATE interface_connections SET paused = $2
+    WHERE id = $1
+"#;
+
+pub const SET_CONNECTION_PAUSED_BY_NAME: &str = r#"
+    UPDATE interface_connections SET paused = $2
+    WHERE name = $1
+"#;
+
+pub const DELETE_CONNECTION_BY_NAME: &str = r#"
+    DELETE FROM interface_connections WHERE name = $1
+"#;
+
+/// The oldest pending message of each unpaused connection, when it is due.
+/// A later message never overtakes an earlier one on the same connection,
+/// and connection rows are locked so only one server handles each.
+pub const GET_NEXT_MESSAGES: &str = r#"
+    SELECT m.id, m.tenant_id, m.connection_id, c.name AS connection, c.direction, c.protocol,
+        m.message_type, m.payload, m.metadata, m.status, m.attempts, m.max_attempts, m.next_attempt_at,
+        m.last_error, m.response, m.replays, m.created_at, m.delivered_at, m.dead_at
+    FROM interface_connections c
+    JOIN LATERAL (
+        SELECT *
+        FROM interface_messages
+        WHERE connection_id = c.id AND status = 'pending'
+        ORDER BY id
+        LIMIT 1
+    ) m ON true
+    WHERE NOT c.paused AND m.next_attempt_at <= NOW()
+    ORDER BY m.next_attempt_at
+    LIMIT $1
+    FOR UPDATE OF c SKIP LOCKED
+"#;
+
+pub const MARK_DELIVERED: &str = r#"
+    UPDATE interface_messages
+    SET status = 'delivered', attempts = attempts + 1, response = $2, delivered_at = NOW(), last_error = NULL
+    WHERE id = $1
+"#;
+
+/// Record a failed attempt and schedule the next one
+pub const RETRY_MESSAGE: &str = r#"
+    UPDATE interface_messages
+    SET attempts = attempts + 1, last_error = $2, response = $3, next_attempt_at = NOW() + make_interval(secs => $4)
+    WHERE id = $1
+"#;
+
+/// Move a message to the dead-letter queue, letting the ones after it go
+pub const DEAD_LETTER_MESSAGE: &str = r#"
+    UPDATE interface_messages
+    SET status = 'dead', attempts = attempts + 1, last_error = $2, response = $3, dead_at = NOW()
+    WHERE id = $1
+"#;
+
+pub const GET_MESSAGE: &str = r#"
+    SELECT m.id, m.tenant_id, m.connection_id, c.name AS connection, c.direction, c.protocol,
+        m.message_type, m.payload, m.metadata, m.status, m.attempts, m.max_attempts, m.next_attempt_at,
+        m.last_error, m.response, m.replays, m.created_at, m.delivered_at, m.dead_at
+    FROM interface_messages m
+    JOIN interface_connections c ON c.id = m.connection_id
+    WHERE m.id = $1
+"#;
+
+pub const LIST_MESSAGES: &str = r#"
+    SELECT m.id, m.tenant_id, m.connection_id, c.name AS connection, c.direction, c.protocol,
+        m.message_type, m.payload, m.metadata, m.status, m.attempts, m.max_attempts, m.next_attempt_at,
+        m.last_error, m.response, m.replays, m.created_at, m.delivered_at, m.dead_at
+    FROM interface_messages m
+    JOIN interface_connections c ON c.id = m.connection_id
+    WHERE ($1::uuid IS NULL OR m.connection_id = $1)
+        AND ($2::text IS NULL OR m.status = $2)
+        AND ($3::text IS NULL OR c.protocol = $3)
+        AND ($4::text IS NULL OR c.direction = $4)
+    ORDER BY m.id DESC
+    LIMIT $5 OFFSET $6
+"#;
+
+/// Put a dead letter back in the queue, due now with fresh attempts
+pub const REPLAY_MESSAGE: &str = r#"
+    UPDATE interface_messages
+    SET status = 'pending', attempts = 0, next_attempt_at = NOW(), replays = replays + 1, dead_at = NULL
+    WHERE id = $1 AND status = 'dead'
+"#;
+
+/// Put every dead letter of a connection back in the queue
+pub const REPLAY_CONNECTION: &str = r#"
+    UPDATE interface_messages
+    SET status = 'pending', attempts = 0, next_attempt_at = NOW(), replays = replays + 1, dead_at = NULL
+    WHERE connection_id = $1 AND status = 'dead'
+"#;
+
+/// Messages waiting to be handled, across tenants under the system context
+pub const COUNT_PENDING_MESSAGES: &str = r#"
+    SELECT COUNT(*) FROM interface_messages WHERE status = 'pending'
+"#;
@@ -0,0 +1,54 @@
+//! Interface Engine Module
+//!
+//! This module provides the durable queue for interface traffic (HL7 v2, FHIR, ABDM) including:
+//! - Inbound and outbound messages stored in the database before they are handled
+//! - Ordered handling per connection, by handlers registered per connection scheme
+//! - Automatic retries with exponential backoff, then a dead-letter queue
+//! - An admin API to inspect connections and messages, pause connections and replay dead letters
+
+#[path = "interface_engine.controller.rs"]
+pub mod interface_engine_controller;
+#[path = "interface_engine.service.rs"]
+pub mod interface_engine_service;
+#[path = "interface_engine.sql.rs"]
+pub mod interface_engine_sql;
+
+pub use interface_engine_controller::InterfaceEngineController;
+pub use interface_engine_service::{
+    Direction, HandlerError, InterfaceEngineService, MessageHandler, MessageStatus, NewMessage, Protocol,
+    QueuedMessage,
+};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Interface Engine Module Configuration
+pub struct InterfaceEngineModule {
+    pub service: Arc<InterfaceEngineService>,
+    pub controller: Arc<InterfaceEngineController>,
+}
+
+impl InterfaceEngineModule {
+    /// Create a new Interface Engine Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(InterfaceEngineService::new(db_pool));
+        let controller = Arc::new(InterfaceEngineController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register interface engine routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<InterfaceEngineService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,383 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::inventory::inventory_service::{
+    ConsumptionLine, ExpiringStock, InventoryItem, IssueRequest, ItemCategory, ItemRequest, ItemStock, LineReceipt,
+    PurchaseOrder, PurchaseOrderRequest, PurchaseOrderStatus, ReceiptRequest, StockTake, StockTakeRequest,
+    StockTransaction, DEFAULT_EXPIRY_WINDOW_DAYS,
+};
+use crate::modules::inventory::InventoryService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Which items to list; all of them when nothing is given
+#[derive(Debug, Default, Deserialize)]
+pub struct ItemQuery {
+    pub category: Option<ItemCategory>,
+    /// Only active items
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// Which purchase orders to list; all of them when no status is given
+#[derive(Debug, Default, Deserialize)]
+pub struct PurchaseOrderQuery {
+    pub status: Option<PurchaseOrderStatus>,
+}
+
+/// Stock received against a purchase order
+#[derive(Debug, Deserialize)]
+pub struct ReceiveRequest {
+    pub lines: Vec<LineReceipt>,
+}
+
+/// How far ahead to look for expiring stock
+#[derive(Debug, Default, Deserialize)]
+pub struct ExpiryQuery {
+    pub within_days: Option<i64>,
+}
+
+/// Period and department of a consumption report; the last 30 days of all
+/// departments unless given
+#[derive(Debug, Default, Deserialize)]
+pub struct ConsumptionQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub department: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Inventory controller for the item master, stock movements, purchase
+/// orders, stock takes and stock reports
+pub struct InventoryController {
+    inventory_service: Arc<InventoryService>,
+}
+
+impl InventoryController {
+    /// Create new controller with injected service
+    pub fn new(inventory_service: Arc<InventoryService>) -> Self {
+        Self { inventory_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/items", Self::list_items, "List inventory items")
+            .post("/items", Self::create_item, "Add an item to the item master")
+            .get("/items/low-stock", Self::low_stock, "List items at or below their reorder level")
+            .get("/items/:id", Self::get_item, "Get inventory item by ID")
+            .put("/items/:id", Self::update_item, "Update inventory item")
+            .delete("/items/:id", Self::delete_item, "Delete inventory item")
+            .get("/items/:id/stock", Self::item_stock, "Get an item's stock on hand per lot")
+            .get("/items/:id/transactions", Self::item_transactions, "List an item's stock transactions")
+            .post("/receipts", Self::receive_stock, "Receive stock other than against a purchase order")
+            .post("/issues", Self::issue_stock, "Issue stock to a department")
+            .get("/purchase-orders", Self::list_purchase_orders, "List purchase orders")
+            .post("/purchase-orders", Self::create_purchase_order, "Draft a purchase order")
+            .get("/purchase-orders/:id", Self::get_purchase_order, "Get purchase order by ID")
+            .post("/purchase-orders/:id/submit", Self::submit_purchase_order, "Place a purchase order")
+            .post("/purchase-orders/:id/cancel", Self::cancel_purchase_order, "Cancel a purchase order")
+            .post("/purchase-orders/:id/receive", Self::receive_purchase_order, "Receive stock against a purchase order")
+            .post("/stock-takes", Self::stock_take, "Record a stock take")
+            .get("/expiring", Self::expiring_stock, "List stock nearing or past its expiry date")
+            .get("/consumption", Self::consumption, "Report stock issued per department and item")
+            .with_state(self.inventory_service.clone())
+    }
+
+    /// Inventory items by SKU
+    pub async fn list_items(
+        State(service): State<Arc<InventoryService>>,
+        Query(query): Query<ItemQuery>,
+    ) -> Result<Json<Vec<InventoryItem>>, ErrorReply> {
+        service
+            .list_items(query.category, query.active)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list items", e))
+    }
+
+    /// Add an item to the item master
+    pub async fn create_item(
+        State(service): State<Arc<InventoryService>>,
+        Json(request): Json<ItemRequest>,
+    ) -> Result<(StatusCode, Json<InventoryItem>), ErrorReply> {
+        tracing::info!("Adding inventory item {}", request.sku);
+
+        match service.create_item(request).await {
+            Ok(item) => Ok((StatusCode::CREATED, Json(item))),
+            Err(e) => Err(Self::error_response("Failed to add item", e)),
+        }
+    }
+
+    /// Get inventory item by ID
+    pub async fn get_item(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<InventoryItem>, ErrorReply> {
+        match service.get_item(id).await {
+            Ok(Some(item)) => Ok(Json(item)),
+            Ok(None) => Err(Self::not_found("Item", id)),
+            Err(e) => Err(Self::error_response("Failed to get item", e)),
+        }
+    }
+
+    /// Replace an item's details
+    pub async fn update_item(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ItemRequest>,
+    ) -> Result<Json<InventoryItem>, ErrorReply> {
+        tracing::info!("Updating inventory item {}", id);
+
+        match service.update_item(id, request).await {
+            Ok(Some(item)) => Ok(Json(item)),
+            Ok(None) => Err(Self::not_found("Item", id)),
+            Err(e) => Err(Self::error_response("Failed to update item", e)),
+        }
+    }
+
+    /// Delete inventory item; its stock transactions are kept
+    pub async fn delete_item(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Deleting inventory item {}", id);
+
+        match service.delete_item(id).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found("Item", id)),
+            Err(e) => Err(Self::error_response("Failed to delete item", e)),
+        }
+    }
+
+    /// An item's stock on hand, in total and per lot
+    pub async fn item_stock(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<ItemStock>, ErrorReply> {
+        match service.item_stock(id).await {
+            Ok(Some(stock)) => Ok(Json(stock)),
+            Ok(None) => Err(Self::not_found("Item", id)),
+            Err(e) => Err(Self::error_response("Failed to get stock", e)),
+        }
+    }
+
+    /// Active items to reorder
+    pub async fn low_stock(State(service): State<Arc<InventoryService>>) -> Result<Json<Vec<ItemStock>>, ErrorReply> {
+        service
+            .low_stock()
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list low stock", e))
+    }
+
+    /// An item's stock transactions, newest first
+    pub async fn item_transactions(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<StockTransaction>>, ErrorReply> {
+        service
+            .transactions(id)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list stock transactions", e))
+    }
+
+    /// Receive stock other than against a purchase order
+    pub async fn receive_stock(
+        State(service): State<Arc<InventoryService>>,
+        headers: HeaderMap,
+        Json(request): Json<ReceiptRequest>,
+    ) -> Result<(StatusCode, Json<StockTransaction>), ErrorReply> {
+        tracing::info!("Receiving {} of item {}", request.quantity, request.item_id);
+
+        match service.receive(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(receipt) => Ok((StatusCode::CREATED, Json(receipt))),
+            Err(e) => Err(Self::error_response("Failed to receive stock", e)),
+        }
+    }
+
+    /// Issue stock to a department; answers with a transaction per lot
+    /// drawn from
+    pub async fn issue_stock(
+        State(service): State<Arc<InventoryService>>,
+        headers: HeaderMap,
+        Json(request): Json<IssueRequest>,
+    ) -> Result<(StatusCode, Json<Vec<StockTransaction>>), ErrorReply> {
+        tracing::info!("Issuing {} of item {} to {}", request.quantity, request.item_id, request.department);
+
+        match service.issue(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(issues) => Ok((StatusCode::CREATED, Json(issues))),
+            Err(e) => Err(Self::error_response("Failed to issue stock", e)),
+        }
+    }
+
+    /// Purchase orders, newest first
+    pub async fn list_purchase_orders(
+        State(service): State<Arc<InventoryService>>,
+        Query(query): Query<PurchaseOrderQuery>,
+    ) -> Result<Json<Vec<PurchaseOrder>>, ErrorReply> {
+        service
+            .list_purchase_orders(query.status)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list purchase orders", e))
+    }
+
+    /// Draft a purchase order
+    pub async fn create_purchase_order(
+        State(service): State<Arc<InventoryService>>,
+        headers: HeaderMap,
+        Json(request): Json<PurchaseOrderRequest>,
+    ) -> Result<(StatusCode, Json<PurchaseOrder>), ErrorReply> {
+        tracing::info!("Drafting purchase order {}", request.order_number);
+
+        match service.create_purchase_order(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(order) => Ok((StatusCode::CREATED, Json(order))),
+            Err(e) => Err(Self::error_response("Failed to draft purchase order", e)),
+        }
+    }
+
+    /// Get purchase order by ID
+    pub async fn get_purchase_order(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<PurchaseOrder>, ErrorReply> {
+        match service.get_purchase_order(id).await {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Purchase order", id)),
+            Err(e) => Err(Self::error_response("Failed to get purchase order", e)),
+        }
+    }
+
+    /// Place a draft purchase order with its supplier
+    pub async fn submit_purchase_order(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<PurchaseOrder>, ErrorReply> {
+        tracing::info!("Placing purchase order {}", id);
+
+        match service.submit_purchase_order(id).await {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Purchase order", id)),
+            Err(e) => Err(Self::error_response("Failed to place purchase order", e)),
+        }
+    }
+
+    /// Cancel a purchase order not yet received in full
+    pub async fn cancel_purchase_order(
+        State(service): State<Arc<InventoryService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<PurchaseOrder>, ErrorReply> {
+        tracing::info!("Cancelling purchase order {}", id);
+
+        match service.cancel_purchase_order(id).await {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Purchase order", id)),
+            Err(e) => Err(Self::error_response("Failed to cancel purchase order", e)),
+        }
+    }
+
+    /// Receive stock against a placed purchase order
+    pub async fn receive_purchase_order(
+        State(service): State<Arc<InventoryService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ReceiveRequest>,
+    ) -> Result<Json<PurchaseOrder>, ErrorReply> {
+        tracing::info!("Receiving {} lines against purchase order {}", request.lines.len(), id);
+
+        match service
+            .receive_purchase_order(id, request.lines, extract_user_from_headers(&headers).ok())
+            .await
+        {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Purchase order", id)),
+            Err(e) => Err(Self::error_response("Failed to receive purchase order", e)),
+        }
+    }
+
+    /// Record a stock take; answers with the adjustments it made
+    pub async fn stock_take(
+        State(service): State<Arc<InventoryService>>,
+        headers: HeaderMap,
+        Json(request): Json<StockTakeRequest>,
+    ) -> Result<(StatusCode, Json<StockTake>), ErrorReply> {
+        tracing::info!("Recording stock take of {} counts", request.counts.len());
+
+        match service.stock_take(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(stock_take) => Ok((StatusCode::CREATED, Json(stock_take))),
+            Err(e) => Err(Self::error_response("Failed to record stock take", e)),
+        }
+    }
+
+    /// Stock expiring within `within_days` days, and stock already expired
+    pub async fn expiring_stock(
+        State(service): State<Arc<InventoryService>>,
+        Query(query): Query<ExpiryQuery>,
+    ) -> Result<Json<Vec<ExpiringStock>>, ErrorReply> {
+        let days = query.within_days.unwrap_or(DEFAULT_EXPIRY_WINDOW_DAYS).clamp(0, 3650);
+        service
+            .expiring(Utc::now().date_naive(), days)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list expiring stock", e))
+    }
+
+    /// Stock issued per department and item over a period
+    pub async fn consumption(
+        State(service): State<Arc<InventoryService>>,
+        Query(query): Query<ConsumptionQuery>,
+    ) -> Result<Json<Vec<ConsumptionLine>>, ErrorReply> {
+        let to = query.to.unwrap_or_else(Utc::now);
+        let from = query.from.unwrap_or(to - Duration::days(30));
+        service
+            .consumption(from, to, query.department)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to report consumption", e))
+    }
+
+    fn not_found(kind: &str, id: Uuid) -> ErrorReply {
+        tracing::warn!("{} not found: {}", kind, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", kind),
+                message: format!("{} with id {} not found", kind, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
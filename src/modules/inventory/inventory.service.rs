@@ -0,0 +1,1076 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::inventory::inventory_stock::{allocate_issue, count_adjustment, LotBalance};
+
+// Import SQL queries from separate file
+use crate::modules::inventory::inventory_sql::*;
+
+/// Transactions of an item listed at once
+pub const MAX_ITEM_TRANSACTIONS: i64 = 500;
+
+/// Purchase orders listed at once
+pub const MAX_PURCHASE_ORDERS: i64 = 200;
+
+/// How far ahead expiring stock is looked for unless asked otherwise
+pub const DEFAULT_EXPIRY_WINDOW_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ItemCategory {
+    /// Used up, e.g. gloves, syringes, reagents
+    Consumable,
+    /// Kept and reused, e.g. infusion pumps, monitors
+    Equipment,
+}
+
+impl ItemCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemCategory::Consumable => "consumable",
+            ItemCategory::Equipment => "equipment",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "consumable" => Some(ItemCategory::Consumable),
+            "equipment" => Some(ItemCategory::Equipment),
+            _ => None,
+        }
+    }
+}
+
+/// What a stock transaction does to stock on hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransactionKind {
+    /// Stock received, from a purchase order or otherwise
+    Receipt,
+    /// Stock issued to a department
+    Issue,
+    /// A correction, e.g. after a stock take
+    Adjustment,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Receipt => "receipt",
+            TransactionKind::Issue => "issue",
+            TransactionKind::Adjustment => "adjustment",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "receipt" => TransactionKind::Receipt,
+            "issue" => TransactionKind::Issue,
+            _ => TransactionKind::Adjustment,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PurchaseOrderStatus {
+    Draft,
+    Ordered,
+    PartiallyReceived,
+    Received,
+    Cancelled,
+}
+
+impl PurchaseOrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PurchaseOrderStatus::Draft => "draft",
+            PurchaseOrderStatus::Ordered => "ordered",
+            PurchaseOrderStatus::PartiallyReceived => "partially-received",
+            PurchaseOrderStatus::Received => "received",
+            PurchaseOrderStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "draft" => Some(PurchaseOrderStatus::Draft),
+            "ordered" => Some(PurchaseOrderStatus::Ordered),
+            "partially-received" => Some(PurchaseOrderStatus::PartiallyReceived),
+            "received" => Some(PurchaseOrderStatus::Received),
+            "cancelled" => Some(PurchaseOrderStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether stock may be received against an order in this status
+    pub fn is_receivable(&self) -> bool {
+        matches!(self, PurchaseOrderStatus::Ordered | PurchaseOrderStatus::PartiallyReceived)
+    }
+
+    /// Whether an order may be cancelled: before it is fully received, which
+    /// closes a partially received order short
+    pub fn is_cancellable(&self) -> bool {
+        matches!(
+            self,
+            PurchaseOrderStatus::Draft | PurchaseOrderStatus::Ordered | PurchaseOrderStatus::PartiallyReceived
+        )
+    }
+}
+
+/// An item of the item master
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryItem {
+    pub id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub category: ItemCategory,
+    /// Unit stock is counted in, e.g. box or each
+    pub unit: String,
+    /// Stock at or below which the item is reordered
+    pub reorder_level: i32,
+    /// Whether receipts must give a lot number and expiry date
+    pub tracks_expiry: bool,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn item_from_row(row: &PgRow) -> InventoryItem {
+    InventoryItem {
+        id: row.get("id"),
+        sku: row.get("sku"),
+        name: row.get("name"),
+        category: ItemCategory::from_string(row.get("category")).unwrap_or(ItemCategory::Consumable),
+        unit: row.get("unit"),
+        reorder_level: row.get("reorder_level"),
+        tracks_expiry: row.get("tracks_expiry"),
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// An item as clients send it to create or replace one
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemRequest {
+    pub sku: String,
+    pub name: String,
+    pub category: ItemCategory,
+    pub unit: String,
+    #[serde(default)]
+    pub reorder_level: i32,
+    #[serde(default)]
+    pub tracks_expiry: bool,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl ItemRequest {
+    fn check(&self) -> Result<(), HimsError> {
+        let invalid = |message: &str| HimsError::ValidationError { message: message.to_string() };
+        if self.sku.trim().is_empty() || self.name.trim().is_empty() || self.unit.trim().is_empty() {
+            return Err(invalid("An item needs a SKU, a name and a unit"));
+        }
+        if self.reorder_level < 0 {
+            return Err(invalid("An item's reorder level cannot be negative"));
+        }
+        Ok(())
+    }
+}
+
+/// An item with the stock on hand, in total and per lot
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemStock {
+    pub item: InventoryItem,
+    pub on_hand: i32,
+    pub below_reorder_level: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lots: Vec<LotBalance>,
+}
+
+/// An entry of the stock ledger
+#[derive(Debug, Clone, Serialize)]
+pub struct StockTransaction {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub kind: TransactionKind,
+    /// Positive when stock came in, negative when it went out
+    pub quantity: i32,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    /// Department stock was issued to
+    pub department: Option<String>,
+    pub purchase_order_id: Option<Uuid>,
+    pub stock_take_id: Option<Uuid>,
+    pub reason: Option<String>,
+    pub recorded_by: Option<Uuid>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl StockTransaction {
+    fn new(item_id: Uuid, kind: TransactionKind, quantity: i32, recorded_by: Option<Uuid>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            item_id,
+            kind,
+            quantity,
+            lot_number: None,
+            expiry_date: None,
+            department: None,
+            purchase_order_id: None,
+            stock_take_id: None,
+            reason: None,
+            recorded_by,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+fn transaction_from_row(row: &PgRow) -> StockTransaction {
+    StockTransaction {
+        id: row.get("id"),
+        item_id: row.get("item_id"),
+        kind: TransactionKind::from_string(row.get("kind")),
+        quantity: row.get("quantity"),
+        lot_number: row.get("lot_number"),
+        expiry_date: row.get("expiry_date"),
+        department: row.get("department"),
+        purchase_order_id: row.get("purchase_order_id"),
+        stock_take_id: row.get("stock_take_id"),
+        reason: row.get("reason"),
+        recorded_by: row.get("recorded_by"),
+        occurred_at: row.get("occurred_at"),
+    }
+}
+
+/// Stock received other than against a purchase order, e.g. a donation
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReceiptRequest {
+    pub item_id: Uuid,
+    pub quantity: i32,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub reason: Option<String>,
+}
+
+/// Stock issued to a department, from a given lot or first expiring first
+#[derive(Debug, Clone, Deserialize)]
+pub struct IssueRequest {
+    pub item_id: Uuid,
+    pub quantity: i32,
+    pub department: String,
+    pub lot_number: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// A line of a purchase order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PurchaseOrderLine {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub quantity_ordered: i32,
+    #[serde(default)]
+    pub quantity_received: i32,
+    /// Price per unit in the smallest unit of the currency, e.g. cents
+    pub unit_price: Option<i64>,
+}
+
+/// An order to a supplier
+#[derive(Debug, Clone, Serialize)]
+pub struct PurchaseOrder {
+    pub id: Uuid,
+    pub order_number: String,
+    pub supplier: String,
+    pub status: PurchaseOrderStatus,
+    pub lines: Vec<PurchaseOrderLine>,
+    pub expected_on: Option<NaiveDate>,
+    pub note: Option<String>,
+    pub created_by: Option<Uuid>,
+    /// When the order was placed with the supplier
+    pub ordered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn purchase_order_from_row(row: &PgRow) -> PurchaseOrder {
+    PurchaseOrder {
+        id: row.get("id"),
+        order_number: row.get("order_number"),
+        supplier: row.get("supplier"),
+        status: PurchaseOrderStatus::from_string(row.get("status")).unwrap_or(PurchaseOrderStatus::Draft),
+        lines: serde_json::from_value(row.get("lines")).unwrap_or_default(),
+        expected_on: row.get("expected_on"),
+        note: row.get("note"),
+        created_by: row.get("created_by"),
+        ordered_at: row.get("ordered_at"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// A line of stock received against a purchase order
+#[derive(Debug, Clone, Deserialize)]
+pub struct LineReceipt {
+    pub line_id: Uuid,
+    pub quantity: i32,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+}
+
+impl PurchaseOrder {
+    /// Record stock received against the order's lines, moving it to
+    /// partially received or, once every line is in full, received. Nothing
+    /// changes unless every receipt is for a line of the order and none
+    /// takes a line over the quantity ordered.
+    pub fn receive(&mut self, receipts: &[LineReceipt]) -> Result<(), String> {
+        if !self.status.is_receivable() {
+            return Err(format!("Purchase order {} is {}", self.order_number, self.status.as_str()));
+        }
+        if receipts.is_empty() {
+            return Err("Nothing received".to_string());
+        }
+        let mut lines = self.lines.clone();
+        for receipt in receipts {
+            if receipt.quantity <= 0 {
+                return Err(format!("Cannot receive a quantity of {}", receipt.quantity));
+            }
+            let line = lines
+                .iter_mut()
+                .find(|line| line.id == receipt.line_id)
+                .ok_or_else(|| format!("Purchase order {} has no line {}", self.order_number, receipt.line_id))?;
+            if line.quantity_received + receipt.quantity > line.quantity_ordered {
+                return Err(format!(
+                    "Line {} has {} of {} outstanding, not {}",
+                    line.id,
+                    line.quantity_ordered - line.quantity_received,
+                    line.quantity_ordered,
+                    receipt.quantity
+                ));
+            }
+            line.quantity_received += receipt.quantity;
+        }
+        self.status = if lines.iter().all(|line| line.quantity_received >= line.quantity_ordered) {
+            PurchaseOrderStatus::Received
+        } else {
+            PurchaseOrderStatus::PartiallyReceived
+        };
+        self.lines = lines;
+        Ok(())
+    }
+}
+
+/// A line of a purchase order as clients send it
+#[derive(Debug, Clone, Deserialize)]
+pub struct PurchaseOrderLineRequest {
+    pub item_id: Uuid,
+    pub quantity: i32,
+    pub unit_price: Option<i64>,
+}
+
+/// A purchase order as clients send it to create one; it starts as a draft
+#[derive(Debug, Clone, Deserialize)]
+pub struct PurchaseOrderRequest {
+    pub order_number: String,
+    pub supplier: String,
+    pub lines: Vec<PurchaseOrderLineRequest>,
+    pub expected_on: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+/// Stock counted in one lot of an item
+#[derive(Debug, Clone, Deserialize)]
+pub struct StockCount {
+    pub item_id: Uuid,
+    pub lot_number: Option<String>,
+    pub counted: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StockTakeRequest {
+    pub counts: Vec<StockCount>,
+    pub note: Option<String>,
+}
+
+/// A stock take and the adjustments it made where the count differed from
+/// the books
+#[derive(Debug, Clone, Serialize)]
+pub struct StockTake {
+    pub id: Uuid,
+    pub counted_at: DateTime<Utc>,
+    pub counted_by: Option<Uuid>,
+    pub note: Option<String>,
+    pub adjustments: Vec<StockTransaction>,
+}
+
+/// A lot in stock nearing or past its expiry date
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpiringStock {
+    pub item_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub lot_number: Option<String>,
+    pub expiry_date: NaiveDate,
+    pub on_hand: i32,
+    pub expired: bool,
+}
+
+/// Stock of an item issued to a department over a period
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsumptionLine {
+    pub department: String,
+    pub item_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub unit: String,
+    pub quantity: i32,
+    /// Issues the quantity was made up of
+    pub issues: i64,
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Database error, or a conflict when a unique constraint was violated
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+async fn record_transaction(conn: &mut PgConnection, transaction: &StockTransaction) -> Result<(), sqlx::Error> {
+    sqlx::query(INSERT_TRANSACTION)
+        .bind(transaction.id)
+        .bind(transaction.item_id)
+        .bind(transaction.kind.as_str())
+        .bind(transaction.quantity)
+        .bind(&transaction.lot_number)
+        .bind(transaction.expiry_date)
+        .bind(&transaction.department)
+        .bind(transaction.purchase_order_id)
+        .bind(transaction.stock_take_id)
+        .bind(&transaction.reason)
+        .bind(transaction.recorded_by)
+        .bind(transaction.occurred_at)
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
+
+async fn lot_balances(conn: &mut PgConnection, item_id: Uuid) -> Result<Vec<LotBalance>, sqlx::Error> {
+    let rows = sqlx::query(GET_LOT_BALANCES).bind(item_id).fetch_all(conn).await?;
+    Ok(rows
+        .iter()
+        .map(|row| LotBalance {
+            lot_number: row.get("lot_number"),
+            expiry_date: row.get("expiry_date"),
+            on_hand: row.get("on_hand"),
+        })
+        .collect())
+}
+
+/// Inventory service for the item master, the stock ledger, purchase
+/// orders and stock takes
+pub struct InventoryService {
+    pool: PgPool,
+}
+
+impl InventoryService {
+    /// Create new inventory service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Add an item to the item master
+    pub async fn create_item(&self, request: ItemRequest) -> Result<InventoryItem, HimsError> {
+        request.check()?;
+        let row = sqlx::query(INSERT_ITEM)
+            .bind(Uuid::new_v4())
+            .bind(request.sku.trim())
+            .bind(&request.name)
+            .bind(request.category.as_str())
+            .bind(&request.unit)
+            .bind(request.reorder_level)
+            .bind(request.tracks_expiry)
+            .bind(request.active)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("An item with SKU {} exists", request.sku.trim())))?;
+        Ok(item_from_row(&row))
+    }
+
+    /// Get item by ID
+    pub async fn get_item(&self, id: Uuid) -> Result<Option<InventoryItem>, HimsError> {
+        let row = sqlx::query(GET_ITEM_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(item_from_row))
+    }
+
+    /// Items by SKU, optionally of one category or active ones only
+    pub async fn list_items(
+        &self,
+        category: Option<ItemCategory>,
+        active_only: bool,
+    ) -> Result<Vec<InventoryItem>, HimsError> {
+        let rows = sqlx::query(LIST_ITEMS)
+            .bind(category.map(|category| category.as_str()))
+            .bind(active_only)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(item_from_row).collect())
+    }
+
+    /// Replace an item's details, or `None` if there is no such item
+    pub async fn update_item(&self, id: Uuid, request: ItemRequest) -> Result<Option<InventoryItem>, HimsError> {
+        request.check()?;
+        let row = sqlx::query(UPDATE_ITEM)
+            .bind(id)
+            .bind(request.sku.trim())
+            .bind(&request.name)
+            .bind(request.category.as_str())
+            .bind(&request.unit)
+            .bind(request.reorder_level)
+            .bind(request.tracks_expiry)
+            .bind(request.active)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("An item with SKU {} exists", request.sku.trim())))?;
+        Ok(row.as_ref().map(item_from_row))
+    }
+
+    /// Soft delete item
+    pub async fn delete_item(&self, id: Uuid) -> Result<bool, HimsError> {
+        let rows_affected = sqlx::query(SOFT_DELETE_ITEM)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?
+            .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    /// An item's stock on hand per lot, or `None` if there is no such item
+    pub async fn item_stock(&self, id: Uuid) -> Result<Option<ItemStock>, HimsError> {
+        let Some(item) = self.get_item(id).await? else {
+            return Ok(None);
+        };
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        let lots = lot_balances(&mut conn, id).await.map_err(database_error)?;
+        let on_hand = lots.iter().map(|lot| lot.on_hand).sum();
+        Ok(Some(ItemStock {
+            below_reorder_level: on_hand <= item.reorder_level,
+            item,
+            on_hand,
+            lots,
+        }))
+    }
+
+    /// Active items at or below their reorder level, to reorder
+    pub async fn low_stock(&self) -> Result<Vec<ItemStock>, HimsError> {
+        let rows = sqlx::query(GET_LOW_STOCK)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| ItemStock {
+                item: item_from_row(row),
+                on_hand: row.get("on_hand"),
+                below_reorder_level: true,
+                lots: Vec::new(),
+            })
+            .collect())
+    }
+
+    /// The item stock is moved for, which must be active
+    async fn stocked_item(conn: &mut PgConnection, item_id: Uuid, lock: bool) -> Result<InventoryItem, HimsError> {
+        let row = sqlx::query(if lock { LOCK_ITEM } else { GET_ITEM_BY_ID })
+            .bind(item_id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?;
+        match row.as_ref().map(item_from_row) {
+            Some(item) if item.active => Ok(item),
+            Some(_) => Err(HimsError::ValidationError {
+                message: format!("Item {} is inactive", item_id),
+            }),
+            None => Err(HimsError::ValidationError {
+                message: format!("Item {} does not exist", item_id),
+            }),
+        }
+    }
+
+    /// Check a receipt of an item says what it must: a positive quantity,
+    /// and the lot and expiry date of items that track expiry
+    fn check_receipt(
+        item: &InventoryItem,
+        quantity: i32,
+        lot_number: &Option<String>,
+        expiry_date: Option<NaiveDate>,
+    ) -> Result<(), HimsError> {
+        if quantity <= 0 {
+            return Err(HimsError::ValidationError {
+                message: format!("Cannot receive a quantity of {}", quantity),
+            });
+        }
+        if item.tracks_expiry && (lot_number.is_none() || expiry_date.is_none()) {
+            return Err(HimsError::ValidationError {
+                message: format!("Item {} tracks expiry; give the lot number and expiry date", item.sku),
+            });
+        }
+        Ok(())
+    }
+
+    /// Receive stock other than against a purchase order
+    pub async fn receive(
+        &self,
+        request: ReceiptRequest,
+        recorded_by: Option<Uuid>,
+    ) -> Result<StockTransaction, HimsError> {
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        let item = Self::stocked_item(&mut conn, request.item_id, false).await?;
+        Self::check_receipt(&item, request.quantity, &request.lot_number, request.expiry_date)?;
+
+        let mut receipt = StockTransaction::new(item.id, TransactionKind::Receipt, request.quantity, recorded_by);
+        receipt.lot_number = request.lot_number;
+        receipt.expiry_date = request.expiry_date;
+        receipt.reason = request.reason;
+        record_transaction(&mut conn, &receipt).await.map_err(database_error)?;
+        Ok(receipt)
+    }
+
+    /// Issue stock to a department, first expiring first unless a lot is
+    /// named, as one transaction per lot drawn from
+    pub async fn issue(
+        &self,
+        request: IssueRequest,
+        recorded_by: Option<Uuid>,
+    ) -> Result<Vec<StockTransaction>, HimsError> {
+        if request.department.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Stock is issued to a department".to_string(),
+            });
+        }
+        if request.quantity <= 0 {
+            return Err(HimsError::ValidationError {
+                message: format!("Cannot issue a quantity of {}", request.quantity),
+            });
+        }
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let item = Self::stocked_item(&mut tx, request.item_id, true).await?;
+        let lots = lot_balances(&mut tx, item.id).await.map_err(database_error)?;
+        let today = Utc::now().date_naive();
+        let takes = allocate_issue(&lots, request.quantity, request.lot_number.as_deref(), today).map_err(|message| {
+            HimsError::ConflictError {
+                message: format!("{} of {}", message, item.sku),
+            }
+        })?;
+
+        let mut issued = Vec::new();
+        for take in takes {
+            let mut issue = StockTransaction::new(item.id, TransactionKind::Issue, -take.quantity, recorded_by);
+            issue.lot_number = take.lot_number;
+            issue.expiry_date = take.expiry_date;
+            issue.department = Some(request.department.trim().to_string());
+            issue.reason = request.reason.clone();
+            record_transaction(&mut tx, &issue).await.map_err(database_error)?;
+            issued.push(issue);
+        }
+        tx.commit().await.map_err(database_error)?;
+        Ok(issued)
+    }
+
+    /// An item's stock transactions, newest first
+    pub async fn transactions(&self, item_id: Uuid) -> Result<Vec<StockTransaction>, HimsError> {
+        let rows = sqlx::query(LIST_ITEM_TRANSACTIONS)
+            .bind(item_id)
+            .bind(MAX_ITEM_TRANSACTIONS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(transaction_from_row).collect())
+    }
+
+    /// Draft a purchase order for active items
+    pub async fn create_purchase_order(
+        &self,
+        request: PurchaseOrderRequest,
+        created_by: Option<Uuid>,
+    ) -> Result<PurchaseOrder, HimsError> {
+        let invalid = |message: String| HimsError::ValidationError { message };
+        if request.order_number.trim().is_empty() || request.supplier.trim().is_empty() {
+            return Err(invalid("A purchase order needs an order number and a supplier".to_string()));
+        }
+        if request.lines.is_empty() {
+            return Err(invalid("A purchase order needs at least one line".to_string()));
+        }
+
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        let mut lines = Vec::new();
+        for line in request.lines {
+            if line.quantity <= 0 {
+                return Err(invalid(format!("Cannot order a quantity of {}", line.quantity)));
+            }
+            if line.unit_price.is_some_and(|price| price < 0) {
+                return Err(invalid("A unit price cannot be negative".to_string()));
+            }
+            Self::stocked_item(&mut conn, line.item_id, false).await?;
+            lines.push(PurchaseOrderLine {
+                id: Uuid::new_v4(),
+                item_id: line.item_id,
+                quantity_ordered: line.quantity,
+                quantity_received: 0,
+                unit_price: line.unit_price,
+            });
+        }
+
+        let order_number = request.order_number.trim().to_string();
+        let row = sqlx::query(INSERT_PURCHASE_ORDER)
+            .bind(Uuid::new_v4())
+            .bind(&order_number)
+            .bind(request.supplier.trim())
+            .bind(PurchaseOrderStatus::Draft.as_str())
+            .bind(serde_json::to_value(&lines).unwrap_or_default())
+            .bind(request.expected_on)
+            .bind(&request.note)
+            .bind(created_by)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| write_error(e, || format!("Purchase order {} exists", order_number)))?;
+        Ok(purchase_order_from_row(&row))
+    }
+
+    /// Get purchase order by ID
+    pub async fn get_purchase_order(&self, id: Uuid) -> Result<Option<PurchaseOrder>, HimsError> {
+        let row = sqlx::query(GET_PURCHASE_ORDER_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(purchase_order_from_row))
+    }
+
+    /// Purchase orders, newest first, optionally in one status only
+    pub async fn list_purchase_orders(
+        &self,
+        status: Option<PurchaseOrderStatus>,
+    ) -> Result<Vec<PurchaseOrder>, HimsError> {
+        let rows = sqlx::query(LIST_PURCHASE_ORDERS)
+            .bind(status.map(|status| status.as_str()))
+            .bind(MAX_PURCHASE_ORDERS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(purchase_order_from_row).collect())
+    }
+
+    /// Move a purchase order on to `status` if `allowed` from where it is, or
+    /// `None` if there is no such order
+    async fn move_purchase_order(
+        &self,
+        id: Uuid,
+        status: PurchaseOrderStatus,
+        allowed: fn(PurchaseOrderStatus) -> bool,
+    ) -> Result<Option<PurchaseOrder>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(order) = sqlx::query(LOCK_PURCHASE_ORDER)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .as_ref()
+            .map(purchase_order_from_row)
+        else {
+            return Ok(None);
+        };
+        if !allowed(order.status) {
+            return Err(HimsError::ConflictError {
+                message: format!(
+                    "Purchase order {} is {} and cannot become {}",
+                    order.order_number,
+                    order.status.as_str(),
+                    status.as_str()
+                ),
+            });
+        }
+        let row = sqlx::query(UPDATE_PURCHASE_ORDER_PROGRESS)
+            .bind(id)
+            .bind(status.as_str())
+            .bind(serde_json::to_value(&order.lines).unwrap_or_default())
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(Some(purchase_order_from_row(&row)))
+    }
+
+    /// Place a draft purchase order with its supplier
+    pub async fn submit_purchase_order(&self, id: Uuid) -> Result<Option<PurchaseOrder>, HimsError> {
+        self.move_purchase_order(id, PurchaseOrderStatus::Ordered, |status| {
+            status == PurchaseOrderStatus::Draft
+        })
+        .await
+    }
+
+    /// Cancel a purchase order that is not yet fully received
+    pub async fn cancel_purchase_order(&self, id: Uuid) -> Result<Option<PurchaseOrder>, HimsError> {
+        self.move_purchase_order(id, PurchaseOrderStatus::Cancelled, |status| status.is_cancellable())
+            .await
+    }
+
+    /// Receive stock against a placed purchase order, or `None` if there is
+    /// no such order
+    pub async fn receive_purchase_order(
+        &self,
+        id: Uuid,
+        receipts: Vec<LineReceipt>,
+        recorded_by: Option<Uuid>,
+    ) -> Result<Option<PurchaseOrder>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut order) = sqlx::query(LOCK_PURCHASE_ORDER)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .as_ref()
+            .map(purchase_order_from_row)
+        else {
+            return Ok(None);
+        };
+        order.receive(&receipts).map_err(|message| match order.status.is_receivable() {
+            true => HimsError::ValidationError { message },
+            false => HimsError::ConflictError { message },
+        })?;
+
+        for receipt in receipts {
+            let Some(line) = order.lines.iter().find(|line| line.id == receipt.line_id) else {
+                continue;
+            };
+            let item = Self::stocked_item(&mut tx, line.item_id, false).await?;
+            Self::check_receipt(&item, receipt.quantity, &receipt.lot_number, receipt.expiry_date)?;
+
+            let mut transaction =
+                StockTransaction::new(item.id, TransactionKind::Receipt, receipt.quantity, recorded_by);
+            transaction.lot_number = receipt.lot_number;
+            transaction.expiry_date = receipt.expiry_date;
+            transaction.purchase_order_id = Some(order.id);
+            record_transaction(&mut tx, &transaction).await.map_err(database_error)?;
+        }
+
+        let row = sqlx::query(UPDATE_PURCHASE_ORDER_PROGRESS)
+            .bind(id)
+            .bind(order.status.as_str())
+            .bind(serde_json::to_value(&order.lines).unwrap_or_default())
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(Some(purchase_order_from_row(&row)))
+    }
+
+    /// Record a stock take, adjusting each counted lot to its count
+    pub async fn stock_take(
+        &self,
+        request: StockTakeRequest,
+        counted_by: Option<Uuid>,
+    ) -> Result<StockTake, HimsError> {
+        if request.counts.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "A stock take needs at least one count".to_string(),
+            });
+        }
+        if let Some(count) = request.counts.iter().find(|count| count.counted < 0) {
+            return Err(HimsError::ValidationError {
+                message: format!("Item {} counted as {}", count.item_id, count.counted),
+            });
+        }
+
+        let mut stock_take = StockTake {
+            id: Uuid::new_v4(),
+            counted_at: Utc::now(),
+            counted_by,
+            note: request.note,
+            adjustments: Vec::new(),
+        };
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        sqlx::query(INSERT_STOCK_TAKE)
+            .bind(stock_take.id)
+            .bind(stock_take.counted_at)
+            .bind(counted_by)
+            .bind(&stock_take.note)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+
+        for count in request.counts {
+            let item = Self::stocked_item(&mut tx, count.item_id, true).await?;
+            let lots = lot_balances(&mut tx, item.id).await.map_err(database_error)?;
+            let (quantity, expiry_date) = count_adjustment(&lots, count.lot_number.as_deref(), count.counted);
+            if quantity == 0 {
+                continue;
+            }
+            let mut adjustment = StockTransaction::new(item.id, TransactionKind::Adjustment, quantity, counted_by);
+            adjustment.lot_number = count.lot_number;
+            adjustment.expiry_date = expiry_date;
+            adjustment.stock_take_id = Some(stock_take.id);
+            adjustment.reason = Some("Stock take".to_string());
+            record_transaction(&mut tx, &adjustment).await.map_err(database_error)?;
+            stock_take.adjustments.push(adjustment);
+        }
+        tx.commit().await.map_err(database_error)?;
+        Ok(stock_take)
+    }
+
+    /// Lots in stock that expire within `days` of `today`, and those that
+    /// already have, soonest first
+    pub async fn expiring(&self, today: NaiveDate, days: i64) -> Result<Vec<ExpiringStock>, HimsError> {
+        let rows = sqlx::query(GET_EXPIRING_STOCK)
+            .bind(today + Duration::days(days))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let expiry_date: NaiveDate = row.get("expiry_date");
+                ExpiringStock {
+                    item_id: row.get("item_id"),
+                    sku: row.get("sku"),
+                    name: row.get("name"),
+                    lot_number: row.get("lot_number"),
+                    expiry_date,
+                    on_hand: row.get("on_hand"),
+                    expired: expiry_date < today,
+                }
+            })
+            .collect())
+    }
+
+    /// Stock issued per department and item in `[from, to)`, optionally to
+    /// one department only
+    pub async fn consumption(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        department: Option<String>,
+    ) -> Result<Vec<ConsumptionLine>, HimsError> {
+        if to <= from {
+            return Err(HimsError::ValidationError {
+                message: "A consumption report's end must be after its start".to_string(),
+            });
+        }
+        let rows = sqlx::query(GET_CONSUMPTION)
+            .bind(from)
+            .bind(to)
+            .bind(department)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| ConsumptionLine {
+                department: row.get("department"),
+                item_id: row.get("item_id"),
+                sku: row.get("sku"),
+                name: row.get("name"),
+                unit: row.get("unit"),
+                quantity: row.get("quantity"),
+                issues: row.get("issues"),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(quantities: &[i32]) -> PurchaseOrder {
+        let now = Utc::now();
+        PurchaseOrder {
+            id: Uuid::new_v4(),
+            order_number: "PO-1".to_string(),
+            supplier: "MedSupply".to_string(),
+            status: PurchaseOrderStatus::Ordered,
+            lines: quantities
+                .iter()
+                .map(|&quantity| PurchaseOrderLine {
+                    id: Uuid::new_v4(),
+                    item_id: Uuid::new_v4(),
+                    quantity_ordered: quantity,
+                    quantity_received: 0,
+                    unit_price: Some(250),
+                })
+                .collect(),
+            expected_on: None,
+            note: None,
+            created_by: None,
+            ordered_at: Some(now),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn receipt(line: &PurchaseOrderLine, quantity: i32) -> LineReceipt {
+        LineReceipt {
+            line_id: line.id,
+            quantity,
+            lot_number: None,
+            expiry_date: None,
+        }
+    }
+
+    #[test]
+    fn test_receive_purchase_order() {
+        let mut po = order(&[10, 5]);
+        let (first, second) = (po.lines[0].clone(), po.lines[1].clone());
+
+        po.receive(&[receipt(&first, 4)]).unwrap();
+        assert_eq!(po.status, PurchaseOrderStatus::PartiallyReceived);
+        assert_eq!(po.lines[0].quantity_received, 4);
+
+        let before = po.lines.clone();
+        assert!(po.receive(&[receipt(&second, 5), receipt(&first, 7)]).is_err(), "over the quantity ordered");
+        assert_eq!(po.lines, before, "a rejected receipt changes nothing");
+
+        po.receive(&[receipt(&first, 6), receipt(&second, 5)]).unwrap();
+        assert_eq!(po.status, PurchaseOrderStatus::Received);
+        assert!(po.receive(&[receipt(&first, 1)]).is_err(), "received in full");
+
+        let mut draft = order(&[1]);
+        draft.status = PurchaseOrderStatus::Draft;
+        let line = draft.lines[0].clone();
+        assert!(draft.receive(&[receipt(&line, 1)]).is_err(), "not yet ordered");
+    }
+
+    #[test]
+    fn test_statuses() {
+        for status in [
+            PurchaseOrderStatus::Draft,
+            PurchaseOrderStatus::Ordered,
+            PurchaseOrderStatus::PartiallyReceived,
+            PurchaseOrderStatus::Received,
+            PurchaseOrderStatus::Cancelled,
+        ] {
+            assert_eq!(PurchaseOrderStatus::from_string(status.as_str()), Some(status));
+        }
+        assert!(PurchaseOrderStatus::PartiallyReceived.is_cancellable());
+        assert!(!PurchaseOrderStatus::Received.is_cancellable());
+        assert_eq!(ItemCategory::from_string("equipment"), Some(ItemCategory::Equipment));
+        assert_eq!(ItemCategory::from_string("drug"), None);
+    }
+}
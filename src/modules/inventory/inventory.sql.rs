@@ -0,0 +1,166 @@
+//! Inventory SQL Queries
+//!
+//! This file contains all SQL queries used by the inventory service.
+
+/// Insert a new item
+pub const INSERT_ITEM: &str = r#"
+    INSERT INTO inventory_items (id, sku, name, category, unit, reorder_level, tracks_expiry, active)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING id, sku, name, category, unit, reorder_level, tracks_expiry, active, created_at, updated_at
+"#;
+
+/// Get item by ID
+pub const GET_ITEM_BY_ID: &str = r#"
+    SELECT id, sku, name, category, unit, reorder_level, tracks_expiry, active, created_at, updated_at
+    FROM inventory_items
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Lock an item while its stock is taken from or counted, so concurrent
+/// issues cannot both spend the same stock
+pub const LOCK_ITEM: &str = r#"
+    SELECT id, sku, name, category, unit, reorder_level, tracks_expiry, active, created_at, updated_at
+    FROM inventory_items
+    WHERE id = $1 AND deleted_at IS NULL
+    FOR UPDATE
+"#;
+
+/// Items, optionally only those of category $1 or active ones ($2), by SKU
+pub const LIST_ITEMS: &str = r#"
+    SELECT id, sku, name, category, unit, reorder_level, tracks_expiry, active, created_at, updated_at
+    FROM inventory_items
+    WHERE deleted_at IS NULL AND ($1::text IS NULL OR category = $1) AND (NOT $2 OR active)
+    ORDER BY sku
+"#;
+
+/// Replace an item's details
+pub const UPDATE_ITEM: &str = r#"
+    UPDATE inventory_items
+    SET sku = $2, name = $3, category = $4, unit = $5, reorder_level = $6, tracks_expiry = $7, active = $8
+    WHERE id = $1 AND deleted_at IS NULL
+    RETURNING id, sku, name, category, unit, reorder_level, tracks_expiry, active, created_at, updated_at
+"#;
+
+/// Soft delete item; its transactions are kept
+pub const SOFT_DELETE_ITEM: &str = r#"
+    UPDATE inventory_items
+    SET deleted_at = NOW(), active = false
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Stock on hand of an item per lot, soonest expiring first
+pub const GET_LOT_BALANCES: &str = r#"
+    SELECT lot_number, MIN(expiry_date) AS expiry_date, SUM(quantity)::integer AS on_hand
+    FROM stock_transactions
+    WHERE item_id = $1
+    GROUP BY lot_number
+    HAVING SUM(quantity) <> 0
+    ORDER BY MIN(expiry_date) NULLS LAST, lot_number NULLS LAST
+"#;
+
+/// Active items whose stock is at or below their reorder level
+pub const GET_LOW_STOCK: &str = r#"
+    SELECT i.id, i.sku, i.name, i.category, i.unit, i.reorder_level, i.tracks_expiry, i.active,
+           i.created_at, i.updated_at, COALESCE(SUM(t.quantity), 0)::integer AS on_hand
+    FROM inventory_items i
+    LEFT JOIN stock_transactions t ON t.item_id = i.id
+    WHERE i.deleted_at IS NULL AND i.active
+    GROUP BY i.id
+    HAVING COALESCE(SUM(t.quantity), 0) <= i.reorder_level
+    ORDER BY i.sku
+"#;
+
+/// Record a stock transaction
+pub const INSERT_TRANSACTION: &str = r#"
+    INSERT INTO stock_transactions (
+        id, item_id, kind, quantity, lot_number, expiry_date, department, purchase_order_id,
+        stock_take_id, reason, recorded_by, occurred_at
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+"#;
+
+/// Transactions of item $1, newest first, at most $2
+pub const LIST_ITEM_TRANSACTIONS: &str = r#"
+    SELECT id, item_id, kind, quantity, lot_number, expiry_date, department, purchase_order_id,
+           stock_take_id, reason, recorded_by, occurred_at
+    FROM stock_transactions
+    WHERE item_id = $1
+    ORDER BY occurred_at DESC, id
+    LIMIT $2
+"#;
+
+/// Lots in stock that expire on or before $1, expired ones first
+pub const GET_EXPIRING_STOCK: &str = r#"
+    SELECT i.id AS item_id, i.sku, i.name, t.lot_number, MIN(t.expiry_date) AS expiry_date,
+           SUM(t.quantity)::integer AS on_hand
+    FROM stock_transactions t
+    JOIN inventory_items i ON i.id = t.item_id
+    WHERE i.deleted_at IS NULL AND t.expiry_date IS NOT NULL
+    GROUP BY i.id, i.sku, i.name, t.lot_number
+    HAVING SUM(t.quantity) > 0 AND MIN(t.expiry_date) <= $1
+    ORDER BY MIN(t.expiry_date), i.sku, t.lot_number
+"#;
+
+/// Stock issued per department and item between $1 and $2, optionally to
+/// department $3 only
+pub const GET_CONSUMPTION: &str = r#"
+    SELECT t.department, i.id AS item_id, i.sku, i.name, i.unit,
+           (-SUM(t.quantity))::integer AS quantity, COUNT(*) AS issues
+    FROM stock_transactions t
+    JOIN inventory_items i ON i.id = t.item_id
+    WHERE t.kind = 'issue' AND t.occurred_at >= $1 AND t.occurred_at < $2
+      AND ($3::text IS NULL OR t.department = $3)
+    GROUP BY t.department, i.id, i.sku, i.name, i.unit
+    ORDER BY t.department, i.sku
+"#;
+
+/// Insert a new purchase order
+pub const INSERT_PURCHASE_ORDER: &str = r#"
+    INSERT INTO purchase_orders (id, order_number, supplier, status, lines, expected_on, note, created_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING id, order_number, supplier, status, lines, expected_on, note, created_by, ordered_at,
+              created_at, updated_at
+"#;
+
+/// Get purchase order by ID
+pub const GET_PURCHASE_ORDER_BY_ID: &str = r#"
+    SELECT id, order_number, supplier, status, lines, expected_on, note, created_by, ordered_at,
+           created_at, updated_at
+    FROM purchase_orders
+    WHERE id = $1
+"#;
+
+/// Lock a purchase order while it is received against
+pub const LOCK_PURCHASE_ORDER: &str = r#"
+    SELECT id, order_number, supplier, status, lines, expected_on, note, created_by, ordered_at,
+           created_at, updated_at
+    FROM purchase_orders
+    WHERE id = $1
+    FOR UPDATE
+"#;
+
+/// Purchase orders, optionally only those in status $1, newest first
+pub const LIST_PURCHASE_ORDERS: &str = r#"
+    SELECT id, order_number, supplier, status, lines, expected_on, note, created_by, ordered_at,
+           created_at, updated_at
+    FROM purchase_orders
+    WHERE $1::text IS NULL OR status = $1
+    ORDER BY created_at DESC
+    LIMIT $2
+"#;
+
+/// Save a purchase order's status and lines; it is marked ordered when it
+/// first leaves draft
+pub const UPDATE_PURCHASE_ORDER_PROGRESS: &str = r#"
+    UPDATE purchase_orders
+    SET status = $2, lines = $3,
+        ordered_at = CASE WHEN ordered_at IS NULL AND $2 <> 'draft' AND $2 <> 'cancelled' THEN NOW() ELSE ordered_at END
+    WHERE id = $1
+    RETURNING id, order_number, supplier, status, lines, expected_on, note, created_by, ordered_at,
+              created_at, updated_at
+"#;
+
+/// Record a stock take
+pub const INSERT_STOCK_TAKE: &str = r#"
+    INSERT INTO stock_takes (id, counted_at, counted_by, note)
+    VALUES ($1, $2, $3, $4)
+"#;
@@ -0,0 +1,132 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Stock of an item on hand in one lot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LotBalance {
+    /// None for stock received without a lot number
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub on_hand: i32,
+}
+
+impl LotBalance {
+    /// Whether the lot is past its expiry date on `today`; stock may be
+    /// used up to and including that date
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.expiry_date.is_some_and(|expiry| expiry < today)
+    }
+}
+
+/// Quantity to take from one lot for an issue
+#[derive(Debug, Clone, PartialEq)]
+pub struct LotTake {
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub quantity: i32,
+}
+
+/// Split an issue of `quantity` across the lots on hand, first expiring
+/// first out, or from lot `lot` only when one is asked for. Expired stock is
+/// never issued. Fails, saying how much could be issued, when there is not
+/// enough.
+pub fn allocate_issue(
+    lots: &[LotBalance],
+    quantity: i32,
+    lot: Option<&str>,
+    today: NaiveDate,
+) -> Result<Vec<LotTake>, String> {
+    if quantity <= 0 {
+        return Err(format!("Cannot issue a quantity of {}", quantity));
+    }
+    let mut usable: Vec<&LotBalance> = lots
+        .iter()
+        .filter(|balance| balance.on_hand > 0 && !balance.is_expired(today))
+        .filter(|balance| lot.is_none_or(|lot| balance.lot_number.as_deref() == Some(lot)))
+        .collect();
+    usable.sort_by(|a, b| match (a.expiry_date, b.expiry_date) {
+        (Some(a_expiry), Some(b_expiry)) => a_expiry.cmp(&b_expiry).then_with(|| a.lot_number.cmp(&b.lot_number)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.lot_number.cmp(&b.lot_number),
+    });
+
+    let available: i32 = usable.iter().map(|balance| balance.on_hand).sum();
+    if available < quantity {
+        return Err(match lot {
+            Some(lot) => format!("Only {} usable in lot {}, not {}", available, lot, quantity),
+            None => format!("Only {} usable in stock, not {}", available, quantity),
+        });
+    }
+
+    let mut remaining = quantity;
+    let mut takes = Vec::new();
+    for balance in usable {
+        if remaining == 0 {
+            break;
+        }
+        let take = remaining.min(balance.on_hand);
+        takes.push(LotTake {
+            lot_number: balance.lot_number.clone(),
+            expiry_date: balance.expiry_date,
+            quantity: take,
+        });
+        remaining -= take;
+    }
+    Ok(takes)
+}
+
+/// Adjustment a stock take makes to a lot: what was counted less what the
+/// books say is on hand. Zero when the count agrees.
+pub fn count_adjustment(lots: &[LotBalance], lot: Option<&str>, counted: i32) -> (i32, Option<NaiveDate>) {
+    let balance = lots.iter().find(|balance| balance.lot_number.as_deref() == lot);
+    let on_hand = balance.map_or(0, |balance| balance.on_hand);
+    (counted - on_hand, balance.and_then(|balance| balance.expiry_date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lot(number: &str, expiry: Option<(i32, u32, u32)>, on_hand: i32) -> LotBalance {
+        LotBalance {
+            lot_number: Some(number.to_string()),
+            expiry_date: expiry.and_then(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d)),
+            on_hand,
+        }
+    }
+
+    #[test]
+    fn test_allocate_first_expiring_first() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let lots = vec![
+            lot("C", None, 100),
+            lot("B", Some((2024, 9, 30)), 10),
+            lot("A", Some((2024, 6, 30)), 5),
+            lot("X", Some((2024, 2, 29)), 50),
+        ];
+
+        let takes = allocate_issue(&lots, 12, None, today).unwrap();
+        let taken: Vec<(&str, i32)> = takes.iter().map(|take| (take.lot_number.as_deref().unwrap(), take.quantity)).collect();
+        assert_eq!(taken, vec![("A", 5), ("B", 7)], "expired lot X is skipped");
+
+        let takes = allocate_issue(&lots, 20, None, today).unwrap();
+        assert_eq!(takes.last().unwrap().lot_number.as_deref(), Some("C"), "undated stock goes last");
+
+        let takes = allocate_issue(&lots, 3, Some("B"), today).unwrap();
+        assert_eq!(takes.len(), 1);
+        assert_eq!(takes[0].quantity, 3);
+
+        assert!(allocate_issue(&lots, 116, None, today).is_err());
+        assert!(allocate_issue(&lots, 1, Some("X"), today).is_err(), "expired lots cannot be asked for");
+        assert!(allocate_issue(&lots, 0, None, today).is_err());
+    }
+
+    #[test]
+    fn test_count_adjustment() {
+        let lots = vec![lot("A", Some((2024, 6, 30)), 5)];
+        assert_eq!(count_adjustment(&lots, Some("A"), 3), (-2, NaiveDate::from_ymd_opt(2024, 6, 30)));
+        assert_eq!(count_adjustment(&lots, Some("A"), 5).0, 0);
+        assert_eq!(count_adjustment(&lots, Some("B"), 4), (4, None), "stock found in an unknown lot");
+    }
+}
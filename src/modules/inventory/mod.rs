@@ -0,0 +1,52 @@
+//! Inventory Module
+//!
+//! This module provides supply-chain basics for consumables and equipment:
+//! - An item master with units, reorder levels and expiry tracking
+//! - A stock ledger of receipts, issues to departments and adjustments,
+//!   kept per lot and issued first expiring first
+//! - Purchase orders, received against in one or more deliveries
+//! - Stock takes, adjusting the books to what was counted
+//! - Low stock, expiring stock and per-department consumption reports
+
+#[path = "inventory.controller.rs"]
+pub mod inventory_controller;
+#[path = "inventory.service.rs"]
+pub mod inventory_service;
+#[path = "inventory.sql.rs"]
+pub mod inventory_sql;
+#[path = "inventory.stock.rs"]
+pub mod inventory_stock;
+
+pub use inventory_controller::InventoryController;
+pub use inventory_service::{InventoryItem, InventoryService, PurchaseOrder, StockTransaction};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Inventory Module Configuration
+pub struct InventoryModule {
+    pub service: Arc<InventoryService>,
+    pub controller: Arc<InventoryController>,
+}
+
+impl InventoryModule {
+    /// Create a new Inventory Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(InventoryService::new(db_pool));
+        let controller = Arc::new(InventoryController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<InventoryService> {
+        self.service.clone()
+    }
+}
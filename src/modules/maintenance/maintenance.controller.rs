@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::maintenance::maintenance_service::{MaintenanceReport, MaintenanceTask};
+use crate::modules::maintenance::MaintenanceService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Maintenance controller for platform admins running cleanup by hand
+pub struct MaintenanceController {
+    maintenance_service: Arc<MaintenanceService>,
+}
+
+impl MaintenanceController {
+    /// Create new controller with injected service
+    pub fn new(maintenance_service: Arc<MaintenanceService>) -> Self {
+        Self { maintenance_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post(
+                "/:task/run",
+                Self::run_task,
+                "Run expired-relationships or expired-sessions cleanup now (platform admin)",
+            )
+            .with_state(self.maintenance_service.clone())
+    }
+
+    /// Run a cleanup task now, answering with the rows it changed
+    /// (platform admin)
+    pub async fn run_task(
+        State(service): State<Arc<MaintenanceService>>,
+        headers: HeaderMap,
+        Path(task): Path<MaintenanceTask>,
+    ) -> Result<Json<MaintenanceReport>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.run_now(actor, task).await {
+            Ok(report) => Ok(Json(report)),
+            Err(e) => Err(Self::error_response("Failed to run maintenance", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,167 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{current_tenant_id, with_tenant, TenantContext, DEFAULT_TENANT_ID};
+use crate::modules::auth::SessionManager;
+use crate::modules::authorization::{Action, AuthorizationStorage, PostgresAuthorizationStorage};
+use crate::modules::metrics::MetricsService;
+use crate::modules::role::RoleService;
+use crate::modules::scheduler::ScheduledJob;
+
+/// Housekeeping the scheduler runs across tenants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MaintenanceTask {
+    /// Deactivate relationship tuples past their expiry, recording their
+    /// removal in the changelog
+    ExpiredRelationships,
+    /// End sessions past their expiry or idle timeout
+    ExpiredSessions,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 2] = [MaintenanceTask::ExpiredRelationships, MaintenanceTask::ExpiredSessions];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaintenanceTask::ExpiredRelationships => "expired-relationships",
+            MaintenanceTask::ExpiredSessions => "expired-sessions",
+        }
+    }
+
+    /// Name of the task's scheduled job
+    pub fn job_name(&self) -> String {
+        format!("cleanup-{}", self.as_str())
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            MaintenanceTask::ExpiredRelationships => "Deactivate relationships past their expiry",
+            MaintenanceTask::ExpiredSessions => "End sessions past their expiry or idle timeout",
+        }
+    }
+
+    /// Default schedule. Access checks already ignore expired relationships
+    /// and sessions, so cleanup only needs to keep up with the changelog
+    /// and session lists.
+    pub fn schedule(&self) -> &'static str {
+        "*/5 * * * *"
+    }
+}
+
+/// What a maintenance run did
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceReport {
+    pub task: MaintenanceTask,
+    pub rows_affected: u64,
+    pub ran_at: DateTime<Utc>,
+}
+
+/// Maintenance service cleaning up expired authorization state
+pub struct MaintenanceService {
+    storage: PostgresAuthorizationStorage,
+    sessions: Arc<SessionManager>,
+    metrics: Arc<MetricsService>,
+    role_service: RoleService,
+}
+
+impl MaintenanceService {
+    /// Create new maintenance service
+    pub fn new(pool: PgPool, sessions: Arc<SessionManager>, metrics: Arc<MetricsService>) -> Self {
+        Self {
+            storage: PostgresAuthorizationStorage::new(pool.clone()),
+            role_service: RoleService::new(pool),
+            sessions,
+            metrics,
+        }
+    }
+
+    /// Run a task over every tenant, recording the rows it changed
+    pub async fn run(&self, task: MaintenanceTask) -> Result<MaintenanceReport, HimsError> {
+        let rows_affected = with_tenant(TenantContext::system(), async {
+            match task {
+                MaintenanceTask::ExpiredRelationships => self
+                    .storage
+                    .cleanup_expired_relationships()
+                    .await
+                    .map_err(|e| HimsError::DatabaseError(e.to_string())),
+                MaintenanceTask::ExpiredSessions => self.sessions.cleanup_expired_sessions().await,
+            }
+        })
+        .await?;
+
+        self.metrics.record_maintenance(task.as_str(), rows_affected);
+        if rows_affected > 0 {
+            tracing::info!("Maintenance {} changed {} rows", task.as_str(), rows_affected);
+        }
+        Ok(MaintenanceReport {
+            task,
+            rows_affected,
+            ran_at: Utc::now(),
+        })
+    }
+
+    /// Run a task now, for platform admins
+    pub async fn run_now(&self, actor: Uuid, task: MaintenanceTask) -> Result<MaintenanceReport, HimsError> {
+        self.require_platform_admin(actor).await?;
+        tracing::info!("Maintenance {} started by {}", task.as_str(), actor);
+        self.run(task).await
+    }
+
+    /// The task as a job for the scheduler
+    pub fn job(self: &Arc<Self>, task: MaintenanceTask) -> Arc<dyn ScheduledJob> {
+        Arc::new(MaintenanceJob {
+            service: self.clone(),
+            task,
+        })
+    }
+
+    /// Tasks reach every tenant, so only platform admins start them
+    async fn require_platform_admin(&self, actor: Uuid) -> Result<(), HimsError> {
+        if current_tenant_id() != DEFAULT_TENANT_ID {
+            return Err(HimsError::SecurityError {
+                message: "Maintenance can only be run from the platform tenant".to_string(),
+            });
+        }
+        if !self.role_service.get_user_permissions(actor).await?.contains(&Action::Configure) {
+            tracing::warn!("User {} lacks {} permission", actor, Action::Configure);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", Action::Configure),
+            });
+        }
+        Ok(())
+    }
+}
+
+struct MaintenanceJob {
+    service: Arc<MaintenanceService>,
+    task: MaintenanceTask,
+}
+
+#[async_trait]
+impl ScheduledJob for MaintenanceJob {
+    async fn run(&self) -> Result<Value, HimsError> {
+        let report = self.service.run(self.task).await?;
+        Ok(json!({ "rows_affected": report.rows_affected }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_names() {
+        for task in MaintenanceTask::ALL {
+            let parsed: MaintenanceTask = serde_json::from_value(json!(task.as_str())).unwrap();
+            assert_eq!(parsed, task);
+        }
+        assert_eq!(MaintenanceTask::ExpiredSessions.job_name(), "cleanup-expired-sessions");
+    }
+}
@@ -0,0 +1,59 @@
+//! Maintenance Module
+//!
+//! This module provides housekeeping run on the scheduler including:
+//! - Deactivation of expired authorization relationships
+//! - Ending of expired and idle sessions
+//! - Metrics on the rows each run changed
+//! - Manual runs for platform admins
+
+#[path = "maintenance.controller.rs"]
+pub mod maintenance_controller;
+#[path = "maintenance.service.rs"]
+pub mod maintenance_service;
+
+pub use maintenance_controller::MaintenanceController;
+pub use maintenance_service::{MaintenanceReport, MaintenanceService, MaintenanceTask};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::auth::SessionManager;
+use crate::modules::metrics::MetricsService;
+use crate::modules::scheduler::SchedulerService;
+use crate::utils::api_router::ApiRouter;
+
+/// Maintenance Module Configuration
+pub struct MaintenanceModule {
+    pub service: Arc<MaintenanceService>,
+    pub controller: Arc<MaintenanceController>,
+}
+
+impl MaintenanceModule {
+    /// Create a new Maintenance Module with dependency injection
+    pub fn new(db_pool: PgPool, sessions: Arc<SessionManager>, metrics: Arc<MetricsService>) -> Self {
+        let service = Arc::new(MaintenanceService::new(db_pool, sessions, metrics));
+        let controller = Arc::new(MaintenanceController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Run each task on `scheduler`
+    pub fn schedule(&self, scheduler: &SchedulerService) {
+        for task in MaintenanceTask::ALL {
+            let job = self.service.job(task);
+            if let Err(e) = scheduler.register(&task.job_name(), task.description(), task.schedule(), job) {
+                tracing::error!("Failed to register job {}: {}", task.job_name(), e);
+            }
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<MaintenanceService> {
+        self.service.clone()
+    }
+}
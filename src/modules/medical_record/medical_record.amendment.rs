@@ -0,0 +1,273 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{DocumentStatus, MedicalRecord};
+
+/// An addendum to a finalized medical record. The record's signed content
+/// is never rewritten; addenda add text and strike passages, which stay
+/// readable as struck text.
+#[derive(Debug, Clone, Serialize)]
+pub struct Amendment {
+    pub id: Uuid,
+    pub medical_record_id: Uuid,
+    /// 1 for a record's first addendum, counting up
+    pub sequence: i32,
+    /// Why the record was amended
+    pub reason: String,
+    pub text: String,
+    /// Passage this addendum supersedes
+    pub struck_text: Option<String>,
+    #[serde(skip)]
+    pub strikes_amendment_id: Option<Uuid>,
+    /// Addendum whose text holds `struck_text`; none for the original content
+    pub strikes_sequence: Option<i32>,
+    /// Record version the addendum produced
+    pub version_id: String,
+    pub amended_by: Option<Uuid>,
+    pub amended_at: DateTime<Utc>,
+}
+
+impl Amendment {
+    pub(crate) fn from_row(row: &PgRow) -> Self {
+        Self {
+            id: row.get("id"),
+            medical_record_id: row.get("medical_record_id"),
+            sequence: row.get("sequence"),
+            reason: row.get("reason"),
+            text: row.get("text"),
+            struck_text: row.get("struck_text"),
+            strikes_amendment_id: row.get("strikes_amendment_id"),
+            strikes_sequence: None,
+            version_id: row.get("version_id"),
+            amended_by: row.get("amended_by"),
+            amended_at: row.get("amended_at"),
+        }
+    }
+}
+
+/// A passage an addendum strikes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Strike {
+    /// The passage, verbatim
+    pub text: String,
+    /// Addendum the passage is in; the original content when absent
+    pub amendment_sequence: Option<i32>,
+}
+
+/// Request to amend a finalized record
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewAmendment {
+    pub reason: String,
+    pub text: String,
+    pub strike: Option<Strike>,
+}
+
+impl NewAmendment {
+    /// Reject an addendum without a reason or text, or striking a passage
+    /// that is not in its target or is struck already. Returns the ID of
+    /// the addendum struck, if the strike is in one.
+    pub fn check(&self, content: &str, amendments: &[Amendment]) -> Result<Option<Uuid>, HimsError> {
+        let invalid = |message: String| HimsError::ValidationError { message };
+        if self.reason.trim().is_empty() {
+            return Err(invalid("A reason for the amendment is required".to_string()));
+        }
+        if self.text.trim().is_empty() {
+            return Err(invalid("An addendum must have text".to_string()));
+        }
+        let Some(strike) = &self.strike else {
+            return Ok(None);
+        };
+        if strike.text.trim().is_empty() {
+            return Err(invalid("The struck passage is empty".to_string()));
+        }
+
+        let (target_id, target_text) = match strike.amendment_sequence {
+            None => (None, content),
+            Some(sequence) => {
+                let amendment = amendments
+                    .iter()
+                    .find(|amendment| amendment.sequence == sequence)
+                    .ok_or_else(|| invalid(format!("There is no addendum {}", sequence)))?;
+                (Some(amendment.id), amendment.text.as_str())
+            }
+        };
+        if !target_text.contains(&strike.text) {
+            return Err(invalid("The struck passage does not appear in the text it strikes".to_string()));
+        }
+        let struck_already = amendments.iter().any(|amendment| {
+            amendment.strikes_amendment_id == target_id
+                && amendment
+                    .struck_text
+                    .as_deref()
+                    .is_some_and(|struck| struck.contains(&strike.text) || strike.text.contains(struck))
+        });
+        if struck_already {
+            return Err(HimsError::ConflictError {
+                message: "The passage overlaps one struck by an earlier addendum".to_string(),
+            });
+        }
+        Ok(target_id)
+    }
+}
+
+/// A passage struck through, and the addendum that struck it
+#[derive(Debug, Clone, Serialize)]
+pub struct StruckPassage {
+    pub text: String,
+    pub by_sequence: i32,
+}
+
+/// Text in an amendment chain with the passages later addenda struck
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainText {
+    pub text: String,
+    pub struck: Vec<StruckPassage>,
+}
+
+/// An addendum in an amendment chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainAmendment {
+    #[serde(flatten)]
+    pub amendment: Amendment,
+    /// Passages of this addendum struck by later ones
+    pub struck: Vec<StruckPassage>,
+}
+
+/// A record's original content followed by every addendum, oldest first,
+/// with what each struck
+#[derive(Debug, Clone, Serialize)]
+pub struct AmendmentChain {
+    pub medical_record_id: Uuid,
+    pub status: DocumentStatus,
+    pub version_id: Option<String>,
+    pub original: ChainText,
+    pub amendments: Vec<ChainAmendment>,
+}
+
+impl AmendmentChain {
+    /// Chain of `record` and its addenda, ordered by sequence
+    pub fn new(record: MedicalRecord, mut amendments: Vec<Amendment>) -> Self {
+        amendments.sort_by_key(|amendment| amendment.sequence);
+        let sequences: Vec<(Uuid, i32)> = amendments.iter().map(|amendment| (amendment.id, amendment.sequence)).collect();
+        for amendment in &mut amendments {
+            amendment.strikes_sequence = amendment
+                .strikes_amendment_id
+                .and_then(|id| sequences.iter().find(|(other, _)| *other == id).map(|(_, sequence)| *sequence));
+        }
+        let struck_in = |target: Option<Uuid>| -> Vec<StruckPassage> {
+            amendments
+                .iter()
+                .filter(|amendment| amendment.strikes_amendment_id == target)
+                .filter_map(|amendment| {
+                    amendment.struck_text.as_ref().map(|text| StruckPassage {
+                        text: text.clone(),
+                        by_sequence: amendment.sequence,
+                    })
+                })
+                .collect()
+        };
+
+        let original = ChainText {
+            text: record.content,
+            struck: struck_in(None),
+        };
+        let chained = amendments
+            .iter()
+            .map(|amendment| ChainAmendment {
+                amendment: amendment.clone(),
+                struck: struck_in(Some(amendment.id)),
+            })
+            .collect();
+
+        Self {
+            medical_record_id: record.id,
+            status: record.status,
+            version_id: record.meta.version_id,
+            original,
+            amendments: chained,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MedicalRecordType, Reference};
+
+    fn amendment(sequence: i32, text: &str, strike: Option<(&str, Option<Uuid>)>) -> Amendment {
+        Amendment {
+            id: Uuid::new_v4(),
+            medical_record_id: Uuid::nil(),
+            sequence,
+            reason: "Transcription error".to_string(),
+            text: text.to_string(),
+            struck_text: strike.map(|(text, _)| text.to_string()),
+            strikes_amendment_id: strike.and_then(|(_, id)| id),
+            strikes_sequence: None,
+            version_id: (sequence + 2).to_string(),
+            amended_by: None,
+            amended_at: Utc::now(),
+        }
+    }
+
+    fn new_amendment(strike: Option<Strike>) -> NewAmendment {
+        NewAmendment {
+            reason: "Dose recorded incorrectly".to_string(),
+            text: "Metformin 500 mg twice daily".to_string(),
+            strike,
+        }
+    }
+
+    #[test]
+    fn test_check_requires_reason_and_text() {
+        let mut request = new_amendment(None);
+        assert_eq!(request.check("Original", &[]).unwrap(), None);
+        request.reason = "  ".to_string();
+        assert!(request.check("Original", &[]).is_err());
+        request.reason = "Typo".to_string();
+        request.text = String::new();
+        assert!(request.check("Original", &[]).is_err());
+    }
+
+    #[test]
+    fn test_check_strikes() {
+        let content = "Metformin 850 mg twice daily";
+        let first = amendment(1, "Follow up in 2 weeks", Some(("850 mg", None)));
+        let amendments = vec![first.clone()];
+
+        let strike = |text: &str, amendment_sequence| Some(Strike { text: text.to_string(), amendment_sequence });
+        assert_eq!(new_amendment(strike("twice daily", None)).check(content, &amendments).unwrap(), None);
+        assert_eq!(new_amendment(strike("2 weeks", Some(1))).check(content, &amendments).unwrap(), Some(first.id));
+        assert!(new_amendment(strike("not there", None)).check(content, &amendments).is_err());
+        assert!(new_amendment(strike("2 weeks", Some(2))).check(content, &amendments).is_err());
+        assert!(matches!(
+            new_amendment(strike("850", None)).check(content, &amendments),
+            Err(HimsError::ConflictError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_chain_marks_struck_passages() {
+        let mut record = MedicalRecord::new(
+            Uuid::new_v4(),
+            MedicalRecordType::ProgressNote,
+            "Metformin 850 mg twice daily".to_string(),
+            Reference { reference: "Practitioner/1".to_string(), display: None },
+        );
+        record.status = DocumentStatus::Amended;
+        let first = amendment(1, "Follow up in 2 weeks", Some(("850 mg", None)));
+        let second = amendment(2, "Follow up in 4 weeks", Some(("2 weeks", Some(first.id))));
+
+        let chain = AmendmentChain::new(record, vec![second, first]);
+        assert_eq!(chain.amendments[0].amendment.sequence, 1);
+        assert_eq!(chain.original.struck[0].text, "850 mg");
+        assert_eq!(chain.original.struck[0].by_sequence, 1);
+        assert_eq!(chain.amendments[0].struck[0].by_sequence, 2);
+        assert_eq!(chain.amendments[1].amendment.strikes_sequence, Some(1));
+        assert!(chain.amendments[1].struck.is_empty());
+    }
+}
@@ -1,33 +1,39 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
     response::Json,
-    routing::{delete, get, post, put},
+    routing::{get, post, put},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::core::HimsError;
 use crate::models::{MedicalRecord, MedicalRecordType, DocumentStatus, Reference, ResourceMeta};
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::medical_record::medical_record_amendment::{Amendment, AmendmentChain, NewAmendment};
+use crate::modules::medical_record::medical_record_service::{
+    MedicalRecordSearch, MedicalRecordSearchResult, MedicalRecordTextSearch, MedicalRecordTextSearchResult,
+};
 use crate::modules::medical_record::MedicalRecordService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::get_user_session_context;
+use crate::utils::etag::{etag, if_match_version, precondition_status, versioned, Versioned};
+use crate::utils::fhir_search::SearchEntryMode;
+use crate::utils::pagination::{page_links, BundleLink};
 use std::sync::Arc;
 
+/// State of the record routes: the record service and the engine deciding
+/// who may see and change records
+type RecordState = (Arc<MedicalRecordService>, Arc<HimsAuthorizationEngine>);
+
 /// Medical Record controller for clinical documentation management
 pub struct MedicalRecordController {
     medical_record_service: Arc<MedicalRecordService>,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-pub struct MedicalRecordQuery {
-    pub patient_id: Option<Uuid>,
-    pub record_type: Option<MedicalRecordType>,
-    pub status: Option<DocumentStatus>,
-    pub author: Option<Uuid>,
-    pub date_from: Option<DateTime<Utc>>,
-    pub date_to: Option<DateTime<Utc>>,
-    pub _count: Option<u32>,
-    pub _offset: Option<u32>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -35,66 +41,124 @@ pub struct MedicalRecordCreateRequest {
     pub patient_id: Uuid,
     pub encounter_id: Option<Uuid>,
     pub record_type: MedicalRecordType,
+    /// Rendered from `structured_data` when empty and a template is named
+    #[serde(default)]
     pub content: String,
     pub author: Reference,
+    /// Note template the record is written with
+    #[serde(default)]
+    pub template_id: Option<String>,
+    /// Values of the template's fields, by field name
+    #[serde(default)]
+    pub structured_data: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct MedicalRecordResponse {
-    pub resourceType: String,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
     pub id: Uuid,
     pub meta: ResourceMeta,
-    pub patientId: Uuid,
-    pub encounterId: Option<Uuid>,
-    pub recordType: MedicalRecordType,
+    #[serde(rename = "patientId")]
+    pub patient_id: Uuid,
+    #[serde(rename = "encounterId")]
+    pub encounter_id: Option<Uuid>,
+    #[serde(rename = "recordType")]
+    pub record_type: MedicalRecordType,
     pub status: DocumentStatus,
     pub subject: Reference,
     pub author: Vec<Reference>,
     pub content: String,
-    pub createdAt: DateTime<Utc>,
-    pub updatedAt: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "templateId")]
+    pub template_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "structuredData")]
+    pub structured_data: Option<serde_json::Value>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct MedicalRecordBundle {
-    pub resourceType: String,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
     pub id: Uuid,
     pub meta: ResourceMeta,
-    pub total: u32,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    pub link: Vec<BundleLink>,
     pub entry: Vec<MedicalRecordBundleEntry>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct MedicalRecordBundleEntry {
-    pub fullUrl: String,
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
     pub resource: MedicalRecordResponse,
+    /// Set for full-text matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<TextMatchSearch>,
+    /// Passages of the content around a full-text match, matched words in
+    /// `<mark>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>,
+}
+
+/// `Bundle.entry.search` of a full-text match
+#[derive(Debug, Serialize)]
+pub struct TextMatchSearch {
+    pub mode: SearchEntryMode,
+    /// Rank of the match; higher is better
+    pub score: f32,
 }
 
 impl MedicalRecordController {
-    /// Create new controller with injected service
-    pub fn new(medical_record_service: Arc<MedicalRecordService>) -> Self {
-        Self { medical_record_service }
+    /// Create new controller with injected service and authorization engine
+    pub fn new(
+        medical_record_service: Arc<MedicalRecordService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        Self {
+            medical_record_service,
+            authorization_engine,
+        }
     }
 
     /// Create router with dependency injection
-    pub fn routes(&self) -> Router {
-        Router::new()
-            .route("/", post(Self::create_record))
-            .route("/", get(Self::search_records))
-            .route("/:id", get(Self::get_record))
-            .route("/:id", put(Self::update_record))
-            .route("/:id", delete(Self::delete_record))
-            .with_state(self.medical_record_service.clone())
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/search", Self::search_text, "Full-text search of medical record content")
+            .post("/", Self::create_record, "Create new medical record")
+            .get("/", Self::search_records, "Search medical records")
+            .get("/:id", Self::get_record, "Get medical record by ID")
+            .put("/:id", Self::update_record, "Update medical record content")
+            .delete("/:id", Self::delete_record, "Delete a medical record by ID")
+            .put("/:id/finalize", Self::finalize_record, "Finalize a medical record")
+            .put("/:id/structured-data", Self::update_structured_data, "Update a templated medical record's structured data")
+            .post("/:id/amendments", Self::amend_record, "Amend a finalized medical record with an addendum")
+            .get("/:id/amendments", Self::amendment_chain, "Get a medical record's amendment chain")
+            .with_state((self.medical_record_service.clone(), self.authorization_engine.clone()))
     }
 
     /// Create new medical record
     pub async fn create_record(
-        State(record_service): State<Arc<MedicalRecordService>>,
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Json(payload): Json<MedicalRecordCreateRequest>,
     ) -> Result<(StatusCode, Json<MedicalRecordResponse>), (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Creating new medical record for patient: {}", payload.patient_id);
         
-        match record_service.create_record_from_request(&payload, "system").await {
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        Self::require(&authorizer, Action::Write, Resource::Patient(payload.patient_id)).await?;
+        match record_service.create_record_from_request(&payload, Some(&auth)).await {
             Ok(record_id) => {
                 tracing::info!("Medical record created successfully: {}", record_id);
                 // Fetch the created record to return it
@@ -137,17 +201,30 @@ impl MedicalRecordController {
         }
     }
 
-    /// Get medical record by ID
+    /// Get medical record by ID; records labelled sensitive need an
+    /// elevated relationship to the patient
     pub async fn get_record(
-        State(record_service): State<Arc<MedicalRecordService>>,
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Path(id): Path<Uuid>,
-    ) -> Result<Json<MedicalRecordResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<Versioned<MedicalRecordResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Retrieving medical record: {}", id);
         
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        if Self::readable(&authorizer, &auth, vec![id], |id| *id).await?.is_empty() {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "Access denied".to_string(),
+                    message: format!("Medical record {} is not available to you", id),
+                }),
+            ));
+        }
         match record_service.get_record_by_uuid(id).await {
             Ok(Some(record)) => {
                 tracing::info!("Medical record retrieved successfully: {}", id);
-                Ok(Json(Self::record_to_response(record)))
+                Ok(versioned(&record.meta.clone(), Self::record_to_response(record)))
             }
             Ok(None) => {
                 tracing::warn!("Medical record not found: {}", id);
@@ -174,12 +251,16 @@ impl MedicalRecordController {
 
     /// Delete a medical record by ID
     pub async fn delete_record(
-        State(record_service): State<Arc<MedicalRecordService>>,
-        Path(id): Path<String>,
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
     ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Deleting medical record: {}", id);
         
-        match record_service.delete_record(&id, "system").await {
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        Self::require(&authorizer, Action::Delete, Resource::MedicalRecord(id)).await?;
+        match record_service.delete_record(&id.to_string(), &auth).await {
             Ok(true) => {
                 tracing::info!("Medical record deleted successfully: {}", id);
                 Ok(StatusCode::NO_CONTENT)
@@ -207,17 +288,34 @@ impl MedicalRecordController {
         }
     }
 
-    /// Search medical records
+    /// Search medical records. Records the user may not read are left out,
+    /// so a page can be short; follow `next` until there is none.
     pub async fn search_records(
-        State(record_service): State<Arc<MedicalRecordService>>,
-        Query(params): Query<MedicalRecordQuery>,
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
     ) -> Result<Json<MedicalRecordBundle>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Searching medical records with params: {:?}", params);
-        
-        match record_service.search_records_by_query(params).await {
-            Ok(records) => {
-                tracing::info!("Found {} medical records", records.len());
-                Ok(Json(Self::records_to_bundle(records)))
+
+        let search = MedicalRecordSearch::from_params(&params).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid search parameters".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+        })?;
+
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        match record_service.search_records(&search).await {
+            Ok(mut result) => {
+                result.records = Self::readable(&authorizer, &auth, result.records, |record| record.id).await?;
+                tracing::info!("Found {} medical records", result.records.len());
+                let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+                Ok(Json(Self::records_to_bundle(result, link)))
             }
             Err(e) => {
                 tracing::error!("Failed to search medical records: {}", e);
@@ -232,23 +330,70 @@ impl MedicalRecordController {
         }
     }
 
-    /// Update medical record content
+    /// Full-text search of record content, ranked best first, with the
+    /// matching passages highlighted. Hits the user may not read are left
+    /// out, so a page can be short; follow `next` until there is none.
+    pub async fn search_text(
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<Json<MedicalRecordBundle>, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Full-text searching medical records with params: {:?}", params);
+
+        let search = MedicalRecordTextSearch::from_params(&params).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Invalid search parameters".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+        })?;
+
+        let mut result = record_service.search_records_text(&search).await.map_err(|e| {
+            tracing::error!("Failed to full-text search medical records: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to search medical records".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+        })?;
+
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        result.hits = Self::readable(&authorizer, &auth, result.hits, |hit| hit.record.id).await?;
+
+        tracing::info!("Found {} medical records by full-text search", result.hits.len());
+        let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+        Ok(Json(Self::hits_to_bundle(result, link)))
+    }
+
+    /// Update medical record content; `If-Match` must name the version
+    /// being replaced
     pub async fn update_record(
-        State(record_service): State<Arc<MedicalRecordService>>,
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Path(id): Path<Uuid>,
         Json(content): Json<String>,
-    ) -> Result<Json<MedicalRecordResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<Versioned<MedicalRecordResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Updating medical record: {}", id);
         
-        match record_service.update_record_content_by_uuid(id, content).await {
+        let expected_version = Self::require_if_match(&headers)?;
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        Self::require(&authorizer, Action::Update, Resource::MedicalRecord(id)).await?;
+        match record_service.update_record_content_by_uuid(id, content, &expected_version, &auth).await {
             Ok(record) => {
                 tracing::info!("Medical record updated successfully: {}", id);
-                Ok(Json(Self::record_to_response(record)))
+                Ok(versioned(&record.meta.clone(), Self::record_to_response(record)))
             }
             Err(e) => {
                 tracing::error!("Failed to update medical record {}: {}", id, e);
                 Err((
-                    StatusCode::BAD_REQUEST,
+                    precondition_status(&e).unwrap_or(StatusCode::BAD_REQUEST),
                     Json(ErrorResponse {
                         error: "Failed to update medical record".to_string(),
                         message: e.to_string(),
@@ -258,22 +403,59 @@ impl MedicalRecordController {
         }
     }
 
-    /// Finalize medical record (mark as final)
+    /// Replace the structured data of a record written with a template and
+    /// re-render its content; `If-Match` must name the version being
+    /// replaced
+    pub async fn update_structured_data(
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(structured_data): Json<serde_json::Value>,
+    ) -> Result<Versioned<MedicalRecordResponse>, (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Updating structured data of medical record: {}", id);
+
+        let expected_version = Self::require_if_match(&headers)?;
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        Self::require(&authorizer, Action::Update, Resource::MedicalRecord(id)).await?;
+        match record_service.update_structured_data(id, structured_data, &expected_version, &auth).await {
+            Ok(record) => Ok(versioned(&record.meta.clone(), Self::record_to_response(record))),
+            Err(e) => {
+                tracing::error!("Failed to update structured data of medical record {}: {}", id, e);
+                Err((
+                    precondition_status(&e).unwrap_or(StatusCode::BAD_REQUEST),
+                    Json(ErrorResponse {
+                        error: "Failed to update medical record".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// Finalize medical record (mark as final); `If-Match` must name the
+    /// version being finalized. Records written with a template must have
+    /// every required field filled in.
     pub async fn finalize_record(
-        State(record_service): State<Arc<MedicalRecordService>>,
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Path(id): Path<Uuid>,
-    ) -> Result<Json<MedicalRecordResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<Versioned<MedicalRecordResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Finalizing medical record: {}", id);
         
-        match record_service.finalize_record_by_uuid(id).await {
+        let expected_version = Self::require_if_match(&headers)?;
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        Self::require(&authorizer, Action::Update, Resource::MedicalRecord(id)).await?;
+        match record_service.finalize_record_by_uuid(id, &expected_version, Some(&auth)).await {
             Ok(record) => {
                 tracing::info!("Medical record finalized successfully: {}", id);
-                Ok(Json(Self::record_to_response(record)))
+                Ok(versioned(&record.meta.clone(), Self::record_to_response(record)))
             }
             Err(e) => {
                 tracing::error!("Failed to finalize medical record {}: {}", id, e);
                 Err((
-                    StatusCode::BAD_REQUEST,
+                    precondition_status(&e).unwrap_or(StatusCode::BAD_REQUEST),
                     Json(ErrorResponse {
                         error: "Failed to finalize medical record".to_string(),
                         message: e.to_string(),
@@ -283,37 +465,237 @@ impl MedicalRecordController {
         }
     }
 
+    /// Amend a finalized record with an addendum, which must give a reason
+    /// and may strike a passage; `If-Match` must name the version being
+    /// amended
+    pub async fn amend_record(
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(amendment): Json<NewAmendment>,
+    ) -> Result<(StatusCode, [(HeaderName, String); 1], Json<Amendment>), (StatusCode, Json<ErrorResponse>)> {
+        tracing::info!("Amending medical record: {}", id);
+
+        let expected_version = Self::require_if_match(&headers)?;
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        Self::require(&authorizer, Action::Update, Resource::MedicalRecord(id)).await?;
+        match record_service.amend_record(id, &amendment, &expected_version, &auth).await {
+            Ok(Some(amendment)) => {
+                tracing::info!("Medical record {} amended by addendum {}", id, amendment.sequence);
+                let version = etag(&amendment.version_id);
+                Ok((StatusCode::CREATED, [(header::ETAG, version)], Json(amendment)))
+            }
+            Ok(None) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Medical record not found".to_string(),
+                    message: format!("Medical record with id {} not found", id),
+                }),
+            )),
+            Err(e) => {
+                tracing::error!("Failed to amend medical record {}: {}", id, e);
+                let status = match &e {
+                    HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+                    _ => precondition_status(&e).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                };
+                Err((
+                    status,
+                    Json(ErrorResponse {
+                        error: "Failed to amend medical record".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// The record's original content and every addendum, oldest first, with
+    /// the passages each struck. Reading it needs the same access as
+    /// reading the record.
+    pub async fn amendment_chain(
+        State((record_service, authorization_engine)): State<RecordState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<AmendmentChain>, (StatusCode, Json<ErrorResponse>)> {
+        let authorizer = Self::authorizer(authorization_engine, &auth, &headers).await?;
+        Self::require(&authorizer, Action::Read, Resource::MedicalRecord(id)).await?;
+        match record_service.amendment_chain(id).await {
+            Ok(Some(chain)) => Ok(Json(chain)),
+            Ok(None) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Medical record not found".to_string(),
+                    message: format!("Medical record with id {} not found", id),
+                }),
+            )),
+            Err(e) => {
+                tracing::error!("Failed to get amendment chain of medical record {}: {}", id, e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to get amendment chain".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// Authorizer for the signed-in user and the purpose of use the request
+    /// declares
+    async fn authorizer(
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+    ) -> Result<FieldAuthorizer, (StatusCode, Json<ErrorResponse>)> {
+        let context = get_user_session_context(auth, headers).await.map_err(|e| {
+            tracing::error!("Failed to get user session context: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Internal server error".to_string(),
+                    message: "Failed to establish session context".to_string(),
+                }),
+            )
+        })?;
+        Ok(FieldAuthorizer::for_request(authorization_engine, auth, context))
+    }
+
+    /// The records among `items` the user may read, by the ID `record_id`
+    /// gives each; the others are withheld. Searches and `GET /:id` go
+    /// through here; the amendment chain checks the same access with
+    /// `require`.
+    async fn readable<T>(
+        authorizer: &FieldAuthorizer,
+        auth: &AuthContext,
+        items: Vec<T>,
+        record_id: impl Fn(&T) -> Uuid,
+    ) -> Result<Vec<T>, (StatusCode, Json<ErrorResponse>)> {
+        let found = items.len();
+        let readable = authorizer
+            .retain(Action::Read, items, |item| Resource::MedicalRecord(record_id(item)))
+            .await
+            .map_err(|e| {
+                tracing::error!("Authorization check failed: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Authorization error".to_string(),
+                        message: "Failed to check authorization".to_string(),
+                    }),
+                )
+            })?;
+        if readable.len() < found {
+            let withheld = found - readable.len();
+            tracing::info!("Withheld {} of {} medical records from user {}", withheld, found, auth.user_id);
+        }
+        Ok(readable)
+    }
+
+    /// Fail with 403 unless the user may take `action` on `resource`
+    async fn require(
+        authorizer: &FieldAuthorizer,
+        action: Action,
+        resource: Resource,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        match authorizer.allowed(action.clone(), resource.clone()).await {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                tracing::warn!("Access denied: {:?} on {:?}", action, resource);
+                Err((
+                    StatusCode::FORBIDDEN,
+                    Json(ErrorResponse {
+                        error: "Access denied".to_string(),
+                        message: format!("{:?} on {:?} is not permitted", action, resource),
+                    }),
+                ))
+            }
+            Err(e) => {
+                tracing::error!("Authorization check failed: {}", e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Authorization error".to_string(),
+                        message: "Failed to check authorization".to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// The version named by `If-Match`, which every change must send
+    fn require_if_match(headers: &HeaderMap) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+        if_match_version(headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the medical record's ETag in If-Match to change it".to_string(),
+                }),
+            )
+        })
+    }
+
     /// Convert MedicalRecord model to response format
     fn record_to_response(record: MedicalRecord) -> MedicalRecordResponse {
         MedicalRecordResponse {
-            resourceType: "DocumentReference".to_string(),
+            resource_type: "DocumentReference".to_string(),
             id: record.id,
             meta: record.meta,
-            patientId: record.patient_id,
-            encounterId: record.encounter_id,
-            recordType: record.record_type,
+            patient_id: record.patient_id,
+            encounter_id: record.encounter_id,
+            record_type: record.record_type,
             status: record.status,
             subject: record.subject,
             author: record.author,
             content: record.content,
-            createdAt: record.created_at,
-            updatedAt: record.updated_at,
+            template_id: record.template_id,
+            structured_data: record.structured_data,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
         }
     }
 
-    /// Convert multiple records to bundle format
-    fn records_to_bundle(records: Vec<MedicalRecord>) -> MedicalRecordBundle {
-        let total = records.len() as u32;
-        let entries: Vec<MedicalRecordBundleEntry> = records
+    /// Convert a page of records to searchset bundle format
+    fn records_to_bundle(result: MedicalRecordSearchResult, link: Vec<BundleLink>) -> MedicalRecordBundle {
+        let entries: Vec<MedicalRecordBundleEntry> = result
+            .records
             .into_iter()
             .map(|record| MedicalRecordBundleEntry {
-                fullUrl: format!("DocumentReference/{}", record.id),
+                full_url: format!("DocumentReference/{}", record.id),
                 resource: Self::record_to_response(record),
+                search: None,
+                highlight: None,
             })
             .collect();
 
+        Self::bundle(result.total, link, entries)
+    }
+
+    /// Convert a page of full-text matches to searchset bundle format
+    fn hits_to_bundle(result: MedicalRecordTextSearchResult, link: Vec<BundleLink>) -> MedicalRecordBundle {
+        let entries = result
+            .hits
+            .into_iter()
+            .map(|hit| MedicalRecordBundleEntry {
+                full_url: format!("DocumentReference/{}", hit.record.id),
+                resource: Self::record_to_response(hit.record),
+                search: Some(TextMatchSearch {
+                    mode: SearchEntryMode::Match,
+                    score: hit.score,
+                }),
+                highlight: Some(hit.highlight),
+            })
+            .collect();
+
+        Self::bundle(None, link, entries)
+    }
+
+    fn bundle(total: Option<i64>, link: Vec<BundleLink>, entries: Vec<MedicalRecordBundleEntry>) -> MedicalRecordBundle {
         MedicalRecordBundle {
-            resourceType: "Bundle".to_string(),
+            resource_type: "Bundle".to_string(),
             id: Uuid::new_v4(),
             meta: ResourceMeta {
                 version_id: Some("1".to_string()),
@@ -322,19 +704,23 @@ impl MedicalRecordController {
                 security: vec![],
                 tag: vec![],
             },
+            bundle_type: "searchset".to_string(),
             total,
+            link,
             entry: entries,
         }
     }
 
     /// Create router for medical record endpoints
-    pub fn router() -> Router<Arc<MedicalRecordService>> {
+    pub fn router() -> Router<RecordState> {
         Router::new()
             .route("/", post(Self::create_record))
             .route("/", get(Self::search_records))
             .route("/:id", get(Self::get_record))
             .route("/:id", put(Self::update_record))
             .route("/:id/finalize", put(Self::finalize_record))
+            .route("/:id/amendments", post(Self::amend_record))
+            .route("/:id/amendments", get(Self::amendment_chain))
     }
 }
 
@@ -367,6 +753,135 @@ impl From<&MedicalRecordCreateRequest> for MedicalRecord {
                 security: vec![],
                 tag: vec![],
             },
+            template_id: request.template_id.clone(),
+            structured_data: request.structured_data.clone(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use crate::modules::auth::auth_jwt::test_tokens;
+    use crate::modules::auth::AuthenticatedUser;
+    use crate::modules::authorization::{AuthorizationConfig, StorageBackend};
+    use crate::modules::medical_record::medical_record_service::MedicalRecordTextHit;
+
+    fn record(content: &str) -> MedicalRecord {
+        let author = Reference {
+            reference: format!("Practitioner/{}", Uuid::new_v4()),
+            display: None,
+        };
+        MedicalRecord::new(Uuid::new_v4(), MedicalRecordType::ProgressNote, content.to_string(), author)
+    }
+
+    #[test]
+    fn test_hits_to_bundle() {
+        let hit = MedicalRecordTextHit {
+            record: record("Patient reports chest pain on exertion."),
+            score: 0.25,
+            highlight: "reports <mark>chest</mark> <mark>pain</mark> on exertion".to_string(),
+        };
+        let id = hit.record.id;
+        let result = MedicalRecordTextSearchResult {
+            hits: vec![hit],
+            next: None,
+            previous: None,
+        };
+        let link = vec![BundleLink {
+            relation: "self".to_string(),
+            url: "/api/v1/medical-records/search?_content=chest+pain".to_string(),
+        }];
+
+        let bundle = serde_json::to_value(MedicalRecordController::hits_to_bundle(result, link)).unwrap();
+        assert_eq!(bundle["type"], "searchset");
+        // A total would count hits withheld from the user
+        assert!(bundle.get("total").is_none());
+        let entry = &bundle["entry"][0];
+        assert_eq!(entry["fullUrl"], format!("DocumentReference/{}", id));
+        assert_eq!(entry["search"], serde_json::json!({ "mode": "match", "score": 0.25 }));
+        assert_eq!(entry["highlight"], "reports <mark>chest</mark> <mark>pain</mark> on exertion");
+    }
+
+    #[test]
+    fn test_records_to_bundle() {
+        let result = MedicalRecordSearchResult {
+            records: vec![record("Routine follow-up.")],
+            total: Some(1),
+            next: None,
+            previous: None,
+        };
+        let bundle = serde_json::to_value(MedicalRecordController::records_to_bundle(result, vec![])).unwrap();
+        assert_eq!(bundle["total"], 1);
+        assert!(bundle["entry"][0].get("search").is_none());
+        assert!(bundle["entry"][0].get("highlight").is_none());
+    }
+
+    /// Controller routes over an engine that grants nothing, and the
+    /// headers of a signed-in clinician treating a patient
+    async fn denying_router() -> (axum::Router, String) {
+        // Never connected: the engine turns requests away before any lookup
+        let pool = sqlx::PgPool::connect_lazy("postgres://localhost/hims").unwrap();
+        let config = AuthorizationConfig {
+            storage_backend: StorageBackend::InMemory,
+            ..AuthorizationConfig::default()
+        };
+        let engine = HimsAuthorizationEngine::from_config(config, None).await.unwrap();
+        let controller = MedicalRecordController::new(Arc::new(MedicalRecordService::new(pool)), Arc::new(engine));
+
+        let tokens = test_tokens(&AuthenticatedUser {
+            id: Uuid::new_v4().to_string(),
+            username: "clinician".to_string(),
+            role: "physician".to_string(),
+            permissions: vec![],
+        });
+        (controller.routes().into_router(), format!("Bearer {}", tokens.access_token))
+    }
+
+    #[tokio::test]
+    async fn test_get_record_withholds_unreadable_records() {
+        let (mut router, authorization) = denying_router().await;
+        let request = Request::get(format!("/{}", Uuid::new_v4()))
+            .header("authorization", authorization)
+            .header("x-purpose-of-use", "treatment")
+            .body(Body::empty())
+            .unwrap();
+        let response = tower::Service::call(&mut router, request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_finalize_need_update_access() {
+        let (mut router, authorization) = denying_router().await;
+        let id = Uuid::new_v4();
+        let requests = [
+            Request::put(format!("/{}", id)).body(Body::from("\"Revised note\"")),
+            Request::put(format!("/{}/structured-data", id)).body(Body::from("{}")),
+            Request::put(format!("/{}/finalize", id)).body(Body::empty()),
+        ];
+        for request in requests {
+            let mut request = request.unwrap();
+            let headers = request.headers_mut();
+            headers.insert("authorization", authorization.parse().unwrap());
+            headers.insert("x-purpose-of-use", "treatment".parse().unwrap());
+            headers.insert("content-type", "application/json".parse().unwrap());
+            headers.insert("if-match", "W/\"1\"".parse().unwrap());
+            let uri = request.uri().clone();
+            let response = tower::Service::call(&mut router, request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", uri);
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_amendment_chain_needs_read_access() {
+        let (mut router, authorization) = denying_router().await;
+        let request = Request::get(format!("/{}/amendments", Uuid::new_v4()))
+            .header("authorization", authorization)
+            .header("x-purpose-of-use", "treatment")
+            .body(Body::empty())
+            .unwrap();
+        let response = tower::Service::call(&mut router, request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
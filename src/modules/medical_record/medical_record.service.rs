@@ -1,33 +1,350 @@
-use sqlx::{PgPool, Row};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
 use uuid::Uuid;
-use anyhow::{Context, Result};
-use chrono::Utc;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
 
-use crate::models::{MedicalRecord, AuditLog, AuditEventType, AuditAction, AuditOutcome};
-use crate::models::{MedicalRecordType, DocumentStatus, Reference, ResourceMeta};
+use crate::models::{MedicalRecord, AuditLog, AuditEventType, AuditAction};
+use crate::models::{MedicalRecordType, DocumentStatus};
 use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::modules::note_template::NoteTemplateService;
+use crate::modules::medical_record::medical_record_amendment::{Amendment, AmendmentChain, NewAmendment};
+use crate::standards::fhir::PackValidator;
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{SearchParamDefinition, TotalMode};
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
 
 // Import SQL queries from separate file
 use crate::modules::medical_record::medical_record_sql::*;
 
+/// Medical record from a row of any query selecting its columns
+fn record_from_row(row: &PgRow) -> MedicalRecord {
+    MedicalRecord {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        record_type: serde_json::from_value(serde_json::Value::String(row.get("record_type"))).unwrap_or_default(),
+        status: serde_json::from_value(serde_json::Value::String(row.get("status"))).unwrap_or_default(),
+        subject: serde_json::from_value(row.get("subject")).unwrap_or_default(),
+        author: serde_json::from_value(row.get("author")).unwrap_or_default(),
+        content: row.get("content"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
+        template_id: row.get("template_id"),
+        structured_data: row.get("structured_data"),
+    }
+}
+
+/// Code of a serde-renamed enum, as the medical_records table stores it
+fn code<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(code)) => code,
+        _ => String::new(),
+    }
+}
+
+/// Page size of a medical record search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 50;
+
+/// Largest page a medical record search returns
+pub const MAX_SEARCH_COUNT: i64 = 200;
+
+/// Paging of medical record searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Order of medical record searches, newest first, as SEARCH_MEDICAL_RECORDS
+/// keys its rows
+const SEARCH_KEYS: [SortKey; 1] = [SortKey::new("created_at", "timestamptz", true)];
+
+/// Order of full-text searches, best match first, as
+/// TEXT_SEARCH_MEDICAL_RECORDS keys its rows
+const TEXT_SEARCH_KEYS: [SortKey; 2] = [
+    SortKey::new("ts_rank_cd(search_vector, search.query)", "real", true),
+    SortKey::new("created_at", "timestamptz", true),
+];
+
+/// Parsed medical record search
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicalRecordSearch {
+    pub patient_id: Option<Uuid>,
+    /// Record type code, e.g. `progress-note`
+    pub record_type: Option<String>,
+    /// Document status code, e.g. `final`
+    pub status: Option<String>,
+    /// Author reference, as `Practitioner/{id}`
+    pub author: Option<String>,
+    /// Created at or after
+    pub date_from: Option<DateTime<Utc>>,
+    /// Created before
+    pub date_to: Option<DateTime<Utc>>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for MedicalRecordSearch {
+    fn default() -> Self {
+        Self {
+            patient_id: None,
+            record_type: None,
+            status: None,
+            author: None,
+            date_from: None,
+            date_to: None,
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// Parsed full-text search: `_content` plus the filters and paging of a
+/// medical record search
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicalRecordTextSearch {
+    pub text: String,
+    pub filters: MedicalRecordSearch,
+}
+
+/// A record matching a full-text search
+#[derive(Debug)]
+pub struct MedicalRecordTextHit {
+    pub record: MedicalRecord,
+    /// Rank of the match; higher is better
+    pub score: f32,
+    /// Passages of the content around the matches, matched words in `<mark>`
+    pub highlight: String,
+}
+
+/// A page of full-text search results, best first. There is no total:
+/// callers drop hits the user may not read, so a count of matches would
+/// reveal records they cannot see.
+#[derive(Debug)]
+pub struct MedicalRecordTextSearchResult {
+    pub hits: Vec<MedicalRecordTextHit>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+/// A page of medical record search results
+#[derive(Debug)]
+pub struct MedicalRecordSearchResult {
+    pub records: Vec<MedicalRecord>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl MedicalRecordSearch {
+    /// Search parameters `from_params` supports, for the CapabilityStatement
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "patient_id",
+            param_type: "reference",
+            documentation: "Id of the patient the record is about",
+        },
+        SearchParamDefinition {
+            name: "record_type",
+            param_type: "token",
+            documentation: "Record type, e.g. progress-note",
+        },
+        SearchParamDefinition {
+            name: "status",
+            param_type: "token",
+            documentation: "Document status, e.g. final",
+        },
+        SearchParamDefinition {
+            name: "author",
+            param_type: "reference",
+            documentation: "An author, as Type/id or a Practitioner id",
+        },
+        SearchParamDefinition {
+            name: "date_from",
+            param_type: "date",
+            documentation: "Created at or after this RFC 3339 time",
+        },
+        SearchParamDefinition {
+            name: "date_to",
+            param_type: "date",
+            documentation: "Created before this RFC 3339 time",
+        },
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored; invalid
+    /// values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        Self::parse(params, &SEARCH_KEYS)
+    }
+
+    /// Parse parameters of a search ordered by `keys`
+    fn parse<'a>(params: impl IntoIterator<Item = &'a (String, String)>, keys: &[SortKey]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+        let date = |name: &str, value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|_| invalid(name, value))
+        };
+
+        for (name, value) in params {
+            let name = name.as_str();
+            match name {
+                "patient_id" => search.patient_id = Some(Uuid::parse_str(value).map_err(|_| invalid(name, value))?),
+                "record_type" => {
+                    serde_json::from_value::<MedicalRecordType>(serde_json::Value::String(value.clone()))
+                        .map_err(|_| invalid(name, value))?;
+                    search.record_type = Some(value.clone());
+                }
+                "status" => {
+                    serde_json::from_value::<DocumentStatus>(serde_json::Value::String(value.clone()))
+                        .map_err(|_| invalid(name, value))?;
+                    search.status = Some(value.clone());
+                }
+                "author" => {
+                    let reference = match Uuid::parse_str(value) {
+                        Ok(id) => format!("Practitioner/{}", id),
+                        Err(_) if value.contains('/') => value.clone(),
+                        Err(_) => return Err(invalid(name, value)),
+                    };
+                    search.author = Some(reference);
+                }
+                "date_from" => search.date_from = Some(date(name, value)?),
+                "date_to" => search.date_to = Some(date(name, value)?),
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported medical record search parameter {}", name),
+            }
+        }
+        search.page.check_cursor(keys)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on medical records for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(patient_id) = self.patient_id {
+            query.push(" AND patient_id = ").push_bind(patient_id);
+        }
+        if let Some(record_type) = &self.record_type {
+            query.push(" AND record_type = ").push_bind(record_type.clone());
+        }
+        if let Some(status) = &self.status {
+            query.push(" AND status = ").push_bind(status.clone());
+        }
+        if let Some(author) = &self.author {
+            query
+                .push(" AND author @> ")
+                .push_bind(serde_json::json!([{ "reference": author }]));
+        }
+        if let Some(date_from) = self.date_from {
+            query.push(" AND created_at >= ").push_bind(date_from);
+        }
+        if let Some(date_to) = self.date_to {
+            query.push(" AND created_at < ").push_bind(date_to);
+        }
+    }
+}
+
+impl MedicalRecordTextSearch {
+    /// Parse query parameters; `_content` is required
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let text = params
+            .iter()
+            .filter(|(name, _)| name == "_content")
+            .map(|(_, value)| value.trim())
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if text.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "_content is required".to_string(),
+            });
+        }
+
+        let filters = MedicalRecordSearch::parse(
+            params.iter().filter(|(name, _)| name != "_content"),
+            &TEXT_SEARCH_KEYS,
+        )?;
+        Ok(Self { text, filters })
+    }
+
+    /// The page of matches this search asks for, best first
+    fn query(&self) -> QueryBuilder<'static, Postgres> {
+        let page = &self.filters.page;
+        let mut query = QueryBuilder::<Postgres>::new(TEXT_SEARCH_QUERY);
+        query.push_bind(self.text.clone()).push(TEXT_SEARCH_MEDICAL_RECORDS);
+        self.filters.push_filters(&mut query);
+        page.push_cursor(&mut query, &TEXT_SEARCH_KEYS);
+        page.push_order(&mut query, &TEXT_SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(page.fetch_limit());
+        query
+    }
+}
+
 /// Medical record service for healthcare business logic
 #[derive(Debug, Clone)]
 pub struct MedicalRecordService {
     pool: PgPool,
+    events: EventBus,
+    templates: NoteTemplateService,
+    /// Profile pack records must conform to, as DocumentReferences, if the
+    /// country has one
+    profiles: Option<PackValidator>,
 }
 
 impl MedicalRecordService {
     /// Create new medical record service
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self::with_events(pool, EventBus::default())
+    }
+
+    /// Create the service publishing to a shared event bus
+    pub fn with_events(pool: PgPool, events: EventBus) -> Self {
+        let templates = NoteTemplateService::new(pool.clone());
+        Self {
+            pool,
+            events,
+            templates,
+            profiles: None,
+        }
+    }
+
+    /// Validate records against a profile pack before they are stored
+    pub fn with_profiles(mut self, profiles: Option<PackValidator>) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    /// Reject a record that does not conform to the profile pack
+    fn conform(&self, record: &MedicalRecord) -> Result<(), HimsError> {
+        match &self.profiles {
+            Some(profiles) => profiles.require(&record.to_document_reference()),
+            None => Ok(()),
+        }
+    }
+
+    /// Reject new content that would leave a record not conforming to the
+    /// profile pack
+    async fn conform_content(&self, id: &str, content: &str) -> Result<(), HimsError> {
+        if self.profiles.is_none() {
+            return Ok(());
+        }
+        let mut record = self
+            .get_medical_record(id)
+            .await?
+            .ok_or(HimsError::DatabaseError(format!("Record not found: {}", id)))?;
+        record.content = content.to_string();
+        self.conform(&record)
     }
 
     /// Create a new medical record with FHIR compliance
     pub async fn create_medical_record(
         &self,
         medical_record: &MedicalRecord,
-        user_id: &str,
+        auth: Option<&AuthContext>,
     ) -> Result<String, HimsError> {
+        self.conform(medical_record)?;
         let mut tx = self.pool.begin().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
@@ -38,14 +355,16 @@ impl MedicalRecordService {
             .bind(&record_id)
             .bind(medical_record.patient_id)
             .bind(medical_record.encounter_id)
-            .bind(serde_json::to_string(&medical_record.record_type).unwrap())
-            .bind(serde_json::to_string(&medical_record.status).unwrap())
+            .bind(code(&medical_record.record_type))
+            .bind(code(&medical_record.status))
             .bind(serde_json::to_value(&medical_record.subject).unwrap())
             .bind(serde_json::to_value(&medical_record.author).unwrap())
             .bind(&medical_record.content)
             .bind(medical_record.created_at)
             .bind(medical_record.updated_at)
             .bind(serde_json::to_value(&medical_record.meta).unwrap())
+            .bind(&medical_record.template_id)
+            .bind(&medical_record.structured_data)
             .execute(&mut *tx)
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
@@ -54,13 +373,23 @@ impl MedicalRecordService {
         self.log_medical_record_audit(
             &mut tx,
             &record_id,
-            user_id,
+            auth,
             AuditEventType::Create,
             Some("Medical record created".to_string()),
         ).await?;
 
         tx.commit().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.events.publish(DomainEvent::RecordCreated {
+            record_id: medical_record.id,
+            patient_id: medical_record.patient_id,
+        });
+        if matches!(medical_record.record_type, MedicalRecordType::DiagnosticReport) {
+            self.events.publish(DomainEvent::ResultReceived {
+                record_id: medical_record.id,
+                patient_id: medical_record.patient_id,
+            });
+        }
 
         Ok(record_id)
     }
@@ -73,24 +402,7 @@ impl MedicalRecordService {
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
-        if let Some(row) = row {
-            let record = MedicalRecord {
-                id: row.get("id"),
-                patient_id: row.get("patient_id"),
-                encounter_id: row.get("encounter_id"),
-                record_type: serde_json::from_str(&row.get::<String, _>("record_type")).unwrap_or_default(),
-                status: serde_json::from_str(&row.get::<String, _>("status")).unwrap_or_default(),
-                subject: serde_json::from_value(row.get("subject")).unwrap_or_default(),
-                author: serde_json::from_value(row.get("author")).unwrap_or_default(),
-                content: row.get("content"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
-            };
-            Ok(Some(record))
-        } else {
-            Ok(None)
-        }
+        Ok(row.as_ref().map(record_from_row))
     }
 
     /// Search medical records by patient ID
@@ -104,27 +416,11 @@ impl MedicalRecordService {
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
-        let records: Result<Vec<MedicalRecord>, _> = rows.into_iter().map(|row| {
-            Ok(MedicalRecord {
-                id: row.get("id"),
-                patient_id: row.get("patient_id"),
-                encounter_id: row.get("encounter_id"),
-                record_type: serde_json::from_str(&row.get::<String, _>("record_type")).unwrap_or_default(),
-                status: serde_json::from_str(&row.get::<String, _>("status")).unwrap_or_default(),
-                subject: serde_json::from_value(row.get("subject")).unwrap_or_default(),
-                author: serde_json::from_value(row.get("author")).unwrap_or_default(),
-                content: row.get("content"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
-            })
-        }).collect();
-
-        records.map_err(|e: serde_json::Error| HimsError::DatabaseError(e.to_string()))
+        Ok(rows.iter().map(record_from_row).collect())
     }
 
     /// Soft delete medical record with audit logging
-    pub async fn delete_medical_record(&self, id: &str, user_id: &str) -> Result<bool, HimsError> {
+    pub async fn delete_medical_record(&self, id: &str, auth: &AuthContext) -> Result<bool, HimsError> {
         let mut tx = self.pool.begin().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
@@ -140,13 +436,14 @@ impl MedicalRecordService {
             self.log_medical_record_audit(
                 &mut tx,
                 id,
-                user_id,
+                Some(auth),
                 AuditEventType::Delete,
                 Some("Medical record soft deleted".to_string()),
             ).await?;
 
             tx.commit().await
                 .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            self.publish(id, |record_id| DomainEvent::RecordDeleted { record_id });
             Ok(true)
         } else {
             tx.rollback().await
@@ -160,26 +457,32 @@ impl MedicalRecordService {
         &self,
         tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         record_id: &str,
-        user_id: &str,
+        auth: Option<&AuthContext>,
         event_type: AuditEventType,
         details: Option<String>,
     ) -> Result<(), HimsError> {
-        let audit_log = AuditLog::new(
-            event_type,
-            AuditAction::Create,
-            "MedicalRecord".to_string(),
-        )
-        .with_user(Uuid::parse_str(user_id).unwrap_or_default())
-        .with_resource(Uuid::parse_str(record_id).unwrap_or_default())
-        .with_details(details.unwrap_or_default());
+        let action = match event_type {
+            AuditEventType::Create => AuditAction::Create,
+            AuditEventType::Delete => AuditAction::Delete,
+            _ => AuditAction::Update,
+        };
+        let mut audit_log = AuditLog::new(event_type, action, "MedicalRecord".to_string())
+            .with_resource(Uuid::parse_str(record_id).unwrap_or_default())
+            .with_details(details.unwrap_or_default());
+        // The server's own records, such as imported ones, have no user
+        if let Some(auth) = auth {
+            audit_log = auth.audit(audit_log);
+        }
 
         sqlx::query(
             r#"
             INSERT INTO audit_logs (
                 id, event_type, user_id, patient_id, appointment_id, resource_type,
-                resource_id, action, outcome, timestamp, details
+                resource_id, action, outcome, timestamp, details,
+                source_ip, user_agent, session_id, purpose_of_use
             ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+                $1::uuid, $2, NULLIF($3, '')::uuid, $4::uuid, $5::uuid, $6, NULLIF($7, '')::uuid, $8, $9, $10, $11,
+                $12::inet, $13, $14, $15
             )
             "#
         )
@@ -194,6 +497,10 @@ impl MedicalRecordService {
         .bind(&audit_log.outcome)
         .bind(audit_log.timestamp)
         .bind(audit_log.details.as_ref())
+        .bind(audit_log.source_ip.as_ref())
+        .bind(audit_log.user_agent.as_ref())
+        .bind(audit_log.session_id.as_ref())
+        .bind(audit_log.purpose_of_use.as_ref())
         .execute(&mut **tx)
         .await
         .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
@@ -202,110 +509,498 @@ impl MedicalRecordService {
     }
 
     // Alias methods for controller compatibility
-    pub async fn create_record(&self, record: &MedicalRecord, user_id: &str) -> Result<String, HimsError> {
-        self.create_medical_record(record, user_id).await
+    pub async fn create_record(&self, record: &MedicalRecord, auth: Option<&AuthContext>) -> Result<String, HimsError> {
+        self.create_medical_record(record, auth).await
     }
 
     pub async fn get_record(&self, id: &str) -> Result<Option<MedicalRecord>, HimsError> {
         self.get_medical_record(id).await
     }
 
-    pub async fn delete_record(&self, id: &str, user_id: &str) -> Result<bool, HimsError> {
-        self.delete_medical_record(id, user_id).await
+    pub async fn delete_record(&self, id: &str, auth: &AuthContext) -> Result<bool, HimsError> {
+        self.delete_medical_record(id, auth).await
+    }
+
+    /// Search medical records, a page at a time
+    pub async fn search_records(&self, search: &MedicalRecordSearch) -> Result<MedicalRecordSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_MEDICAL_RECORDS);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &SEARCH_KEYS);
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut fetched = Vec::new();
+        for row in rows {
+            let record = record_from_row(&row);
+            fetched.push((record, RowKey::from_column(row.get("page_key"))?));
+        }
+        let page = search.page.page(fetched);
+
+        let total = count_matches(
+            &self.pool,
+            search.page.total(),
+            COUNT_MEDICAL_RECORDS,
+            ESTIMATE_MEDICAL_RECORDS,
+            |query| search.push_filters(query),
+        )
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(MedicalRecordSearchResult {
+            records: page.items,
+            total,
+            next: page.next,
+            previous: page.previous,
+        })
     }
 
-    pub async fn search_records(&self, params: serde_json::Value) -> Result<Vec<MedicalRecord>, HimsError> {
-        // Simple implementation that returns a basic list for now
-        // TODO: Implement proper filtering based on params
-        let rows = sqlx::query("SELECT id, patient_id, encounter_id, record_type, status, content, created_at, updated_at FROM medical_records ORDER BY created_at DESC LIMIT 100")
+    /// Full-text search of record content, best match first, a page at a
+    /// time
+    pub async fn search_records_text(&self, search: &MedicalRecordTextSearch) -> Result<MedicalRecordTextSearchResult, HimsError> {
+        let page = &search.filters.page;
+        let rows = search
+            .query()
+            .build()
             .fetch_all(&self.pool)
             .await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
 
-        let mut records = Vec::new();
+        let mut fetched = Vec::new();
         for row in rows {
-            let record = MedicalRecord {
-                id: row.get::<String, _>("id").parse().unwrap_or_else(|_| Uuid::new_v4()),
-                patient_id: row.get::<String, _>("patient_id").parse().unwrap_or_else(|_| Uuid::new_v4()),
-                encounter_id: row.get::<Option<String>, _>("encounter_id").and_then(|s| s.parse().ok()),
-                record_type: MedicalRecordType::from_string(&row.get::<String, _>("record_type")),
-                status: DocumentStatus::from_string(&row.get::<String, _>("status")),
-                subject: Reference {
-                    reference: format!("Patient/{}", row.get::<String, _>("patient_id")),
-                    display: None,
-                },
-                author: vec![],
-                content: row.get("content"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-                meta: ResourceMeta {
-                    version_id: Some("1".to_string()),
-                    last_updated: row.get("updated_at"),
-                    profile: vec!["http://hl7.org/fhir/StructureDefinition/DocumentReference".to_string()],
-                    security: vec![],
-                    tag: vec![],
-                },
+            let hit = MedicalRecordTextHit {
+                record: record_from_row(&row),
+                score: row.get("score"),
+                highlight: row.get("highlight"),
             };
-            records.push(record);
+            fetched.push((hit, RowKey::from_column(row.get("page_key"))?));
         }
-        Ok(records)
+        let page = page.page(fetched);
+
+        Ok(MedicalRecordTextSearchResult {
+            hits: page.items,
+            next: page.next,
+            previous: page.previous,
+        })
     }
 
-    pub async fn update_record_content(&self, id: &str, content: serde_json::Value) -> Result<MedicalRecord, HimsError> {
+    /// Replace a record's content if it is still at `expected_version`
+    pub async fn update_record_content(
+        &self,
+        id: &str,
+        content: serde_json::Value,
+        expected_version: &str,
+        auth: &AuthContext,
+    ) -> Result<MedicalRecord, HimsError> {
+        self.check_editable(id).await?;
+        self.check_version(id, expected_version).await?;
+
         let content_str = content.to_string();
-        sqlx::query("UPDATE medical_records SET content = $1, updated_at = NOW() WHERE id = $2")
+        self.conform_content(id, &content_str).await?;
+        let mut tx = self.pool.begin().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let rows_affected = sqlx::query(UPDATE_MEDICAL_RECORD_CONTENT)
             .bind(&content_str)
             .bind(id)
-            .execute(&self.pool)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .execute(&mut *tx)
             .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        Self::check_applied(id, rows_affected)?;
+        self.log_medical_record_audit(
+            &mut tx,
+            id,
+            Some(auth),
+            AuditEventType::Update,
+            Some("Medical record content updated".to_string()),
+        ).await?;
+        tx.commit().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.publish(id, |record_id| DomainEvent::RecordUpdated { record_id });
         
         self.get_medical_record(id).await?.ok_or(HimsError::DatabaseError("Record not found after update".to_string()))
     }
 
-    pub async fn finalize_record(&self, id: &str) -> Result<MedicalRecord, HimsError> {
-        sqlx::query("UPDATE medical_records SET status = 'FINAL', updated_at = NOW() WHERE id = $1")
+    /// Finalize a record if it is still at `expected_version`. `auth` is
+    /// `None` when the server finalizes a record itself, as on report
+    /// sign-off.
+    pub async fn finalize_record(
+        &self,
+        id: &str,
+        expected_version: &str,
+        auth: Option<&AuthContext>,
+    ) -> Result<MedicalRecord, HimsError> {
+        self.check_version(id, expected_version).await?;
+        self.check_template_complete(id).await?;
+
+        let mut tx = self.pool.begin().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let rows_affected = sqlx::query(FINALIZE_MEDICAL_RECORD)
             .bind(id)
-            .execute(&self.pool)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .execute(&mut *tx)
             .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        Self::check_applied(id, rows_affected)?;
+        self.log_medical_record_audit(
+            &mut tx,
+            id,
+            auth,
+            AuditEventType::Update,
+            Some("Medical record finalized".to_string()),
+        ).await?;
+        tx.commit().await
             .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.publish(id, |record_id| DomainEvent::RecordFinalized { record_id });
         
         self.get_medical_record(id).await?.ok_or(HimsError::DatabaseError("Record not found after finalization".to_string()))
     }
 
+    /// Replace the structured data of a record written with a template, if
+    /// it is still at `expected_version`, and re-render its content from
+    /// the data. Required fields may still be empty; finalizing checks them.
+    pub async fn update_structured_data(
+        &self,
+        id: Uuid,
+        structured_data: serde_json::Value,
+        expected_version: &str,
+        auth: &AuthContext,
+    ) -> Result<MedicalRecord, HimsError> {
+        let id = id.to_string();
+        self.check_editable(&id).await?;
+        self.check_version(&id, expected_version).await?;
+
+        let (template_id, _) = self.structured_data(&id).await?;
+        let template_id = template_id.ok_or_else(|| HimsError::ValidationError {
+            message: format!("Record {} was not written with a note template", id),
+        })?;
+        let template = self.templates.require(&template_id).await?;
+        template.check(&structured_data, false)?;
+        let content = template.render(&structured_data);
+        self.conform_content(&id, &content).await?;
+
+        let mut tx = self.pool.begin().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let rows_affected = sqlx::query(UPDATE_MEDICAL_RECORD_STRUCTURED_DATA)
+            .bind(&structured_data)
+            .bind(&content)
+            .bind(&id)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        Self::check_applied(&id, rows_affected)?;
+        self.log_medical_record_audit(
+            &mut tx,
+            &id,
+            Some(auth),
+            AuditEventType::Update,
+            Some(format!("Medical record structured data updated with template {}", template_id)),
+        ).await?;
+        tx.commit().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.publish(&id, |record_id| DomainEvent::RecordUpdated { record_id });
+
+        self.get_medical_record(&id).await?.ok_or(HimsError::DatabaseError("Record not found after update".to_string()))
+    }
+
+    /// Template and structured data of a record
+    async fn structured_data(&self, id: &str) -> Result<(Option<String>, Option<serde_json::Value>), HimsError> {
+        let row = sqlx::query(GET_MEDICAL_RECORD_STRUCTURED_DATA)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or(HimsError::DatabaseError(format!("Record not found: {}", id)))?;
+        Ok((row.get("template_id"), row.get("structured_data")))
+    }
+
+    /// Fail with `ValidationError` if the record was written with a
+    /// template and a required field has no value
+    async fn check_template_complete(&self, id: &str) -> Result<(), HimsError> {
+        let (template_id, structured_data) = self.structured_data(id).await?;
+        let Some(template_id) = template_id else {
+            return Ok(());
+        };
+        let template = self.templates.require(&template_id).await?;
+        template.check(&structured_data.unwrap_or(serde_json::Value::Null), true)
+    }
+
+    /// Amend a finalized record with an addendum if it is still at
+    /// `expected_version`. Returns `None` if there is no such record.
+    pub async fn amend_record(
+        &self,
+        id: Uuid,
+        amendment: &NewAmendment,
+        expected_version: &str,
+        auth: &AuthContext,
+    ) -> Result<Option<Amendment>, HimsError> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        // Locked until commit, so concurrent addenda take turns for a sequence
+        let Some(row) = sqlx::query(LOCK_MEDICAL_RECORD_FOR_AMENDMENT)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+        let status: String = row.get("status");
+        let current_version: String = row.get("version_id");
+        let content: String = row.get("content");
+        if status != "final" && status != "amended" {
+            return Err(HimsError::ConflictError {
+                message: format!("Record {} is {}; only final records are amended", id, status),
+            });
+        }
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Record {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+
+        let amendments: Vec<Amendment> = sqlx::query(LIST_AMENDMENTS)
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Amendment::from_row)
+            .collect();
+        let strikes_amendment_id = amendment.check(&content, &amendments)?;
+        let sequence = amendments.iter().map(|amendment| amendment.sequence).max().unwrap_or(0) + 1;
+        let version_id = next_version_id(Some(&current_version));
+
+        sqlx::query(MARK_MEDICAL_RECORD_AMENDED)
+            .bind(id)
+            .bind(&version_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let row = sqlx::query(INSERT_AMENDMENT)
+            .bind(Uuid::new_v4())
+            .bind(id)
+            .bind(sequence)
+            .bind(amendment.reason.trim())
+            .bind(&amendment.text)
+            .bind(amendment.strike.as_ref().map(|strike| strike.text.clone()))
+            .bind(strikes_amendment_id)
+            .bind(&version_id)
+            .bind(auth.user_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let mut created = Amendment::from_row(&row);
+        created.strikes_sequence = amendment.strike.as_ref().and_then(|strike| strike.amendment_sequence);
+
+        self.log_medical_record_audit(
+            &mut tx,
+            &id.to_string(),
+            Some(auth),
+            AuditEventType::Update,
+            Some(format!("Medical record amended by addendum {}: {}", sequence, created.reason)),
+        ).await?;
+
+        tx.commit().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.events.publish(DomainEvent::RecordAmended { record_id: id, sequence });
+
+        Ok(Some(created))
+    }
+
+    /// The record's original content and every addendum to it, oldest
+    /// first. Returns `None` if there is no such record.
+    pub async fn amendment_chain(&self, id: Uuid) -> Result<Option<AmendmentChain>, HimsError> {
+        let Some(record) = self.get_record_by_uuid(id).await? else {
+            return Ok(None);
+        };
+        let amendments = sqlx::query(LIST_AMENDMENTS)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Amendment::from_row)
+            .collect();
+        Ok(Some(AmendmentChain::new(record, amendments)))
+    }
+
+    /// Fail with `ConflictError` unless the record is still preliminary;
+    /// final records change only by addenda
+    async fn check_editable(&self, id: &str) -> Result<(), HimsError> {
+        let status: String = sqlx::query_scalar(GET_MEDICAL_RECORD_STATUS)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or(HimsError::DatabaseError(format!("Record not found: {}", id)))?;
+        if status != "preliminary" {
+            return Err(HimsError::ConflictError {
+                message: format!("Record {} is {} and cannot be edited; amend it with an addendum", id, status),
+            });
+        }
+        Ok(())
+    }
+
+    /// Fail with `PreconditionFailed` unless the record is at `expected_version`
+    async fn check_version(&self, id: &str, expected_version: &str) -> Result<(), HimsError> {
+        let current_version: String = sqlx::query_scalar(GET_MEDICAL_RECORD_VERSION)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or(HimsError::DatabaseError(format!("Record not found: {}", id)))?;
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Record {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+        Ok(())
+    }
+
+    /// Publish an event about the record `id`, which is always a UUID for
+    /// records this service wrote
+    fn publish(&self, id: &str, event: impl FnOnce(Uuid) -> DomainEvent) {
+        if let Ok(record_id) = Uuid::parse_str(id) {
+            self.events.publish(event(record_id));
+        }
+    }
+
+    /// A version-guarded update that touched no rows lost a race with a
+    /// concurrent edit
+    fn check_applied(id: &str, rows_affected: u64) -> Result<(), HimsError> {
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Record {} was modified concurrently", id),
+            });
+        }
+        Ok(())
+    }
+
     // Controller adapter methods
     pub async fn create_record_from_request(
         &self,
         request: &crate::modules::medical_record::medical_record_controller::MedicalRecordCreateRequest,
-        user_id: &str,
+        auth: Option<&AuthContext>,
     ) -> Result<String, HimsError> {
-        let record: MedicalRecord = request.into();
-        self.create_medical_record(&record, user_id).await
+        let mut record: MedicalRecord = request.into();
+        if let Some(template_id) = &record.template_id {
+            let template = self.templates.require(template_id).await?;
+            let structured_data = record.structured_data.get_or_insert_with(|| serde_json::json!({}));
+            template.check(structured_data, false)?;
+            if record.content.trim().is_empty() {
+                record.content = template.render(structured_data);
+            }
+        }
+        self.create_medical_record(&record, auth).await
     }
 
     pub async fn get_record_by_uuid(&self, id: uuid::Uuid) -> Result<Option<MedicalRecord>, HimsError> {
         self.get_medical_record(&id.to_string()).await
     }
 
-    pub async fn search_records_by_query(
-        &self,
-        query: crate::modules::medical_record::medical_record_controller::MedicalRecordQuery,
-    ) -> Result<Vec<MedicalRecord>, HimsError> {
-        // Convert query to JSON for now
-        let params = serde_json::to_value(query).unwrap_or(serde_json::Value::Null);
-        self.search_records(params).await
-    }
-
     pub async fn update_record_content_by_uuid(
         &self,
         id: uuid::Uuid,
         content: String,
+        expected_version: &str,
+        auth: &AuthContext,
     ) -> Result<MedicalRecord, HimsError> {
         let content_value = serde_json::Value::String(content);
-        self.update_record_content(&id.to_string(), content_value).await
+        self.update_record_content(&id.to_string(), content_value, expected_version, auth).await
+    }
+
+    pub async fn finalize_record_by_uuid(
+        &self,
+        id: uuid::Uuid,
+        expected_version: &str,
+        auth: Option<&AuthContext>,
+    ) -> Result<MedicalRecord, HimsError> {
+        self.finalize_record(&id.to_string(), expected_version, auth).await
     }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::pagination::PageDirection;
 
-    pub async fn finalize_record_by_uuid(&self, id: uuid::Uuid) -> Result<MedicalRecord, HimsError> {
-        self.finalize_record(&id.to_string()).await
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
     }
-}
\ No newline at end of file
+
+    fn cursor(key: &[&str]) -> String {
+        Cursor {
+            direction: PageDirection::Next,
+            key: key.iter().map(|value| Some(value.to_string())).collect(),
+            id: Uuid::new_v4(),
+        }
+        .encode()
+    }
+
+    #[test]
+    fn test_text_search_params() {
+        let patient_id = Uuid::new_v4();
+        let search = MedicalRecordTextSearch::from_params(&params(&[
+            ("_content", " chest pain "),
+            ("patient_id", &patient_id.to_string()),
+            ("_content", "\"shortness of breath\""),
+            ("status", "final"),
+            ("_count", "5"),
+        ]))
+        .unwrap();
+        assert_eq!(search.text, "chest pain \"shortness of breath\"");
+        assert_eq!(search.filters.patient_id, Some(patient_id));
+        assert_eq!(search.filters.status.as_deref(), Some("final"));
+        assert_eq!(search.filters.page.count, 5);
+
+        for missing in [params(&[]), params(&[("_content", "  ")]), params(&[("status", "final")])] {
+            assert!(matches!(
+                MedicalRecordTextSearch::from_params(&missing),
+                Err(HimsError::ValidationError { .. })
+            ));
+        }
+        assert!(MedicalRecordTextSearch::from_params(&params(&[("_content", "pain"), ("status", "lost")])).is_err());
+    }
+
+    #[test]
+    fn test_text_search_cursor() {
+        // Pages continue from a rank and creation time; a cursor from a
+        // plain search, keyed on creation time alone, does not fit
+        let ranked = cursor(&["0.3", "2024-03-01 10:00:00+00"]);
+        assert!(MedicalRecordTextSearch::from_params(&params(&[("_content", "pain"), ("_cursor", &ranked)])).is_ok());
+        assert!(MedicalRecordSearch::from_params(&params(&[("_cursor", &ranked)])).is_err());
+
+        let plain = cursor(&["2024-03-01 10:00:00+00"]);
+        assert!(MedicalRecordTextSearch::from_params(&params(&[("_content", "pain"), ("_cursor", &plain)])).is_err());
+    }
+
+    #[test]
+    fn test_text_search_sql() {
+        let search = MedicalRecordTextSearch::from_params(&params(&[
+            ("_content", "pneumonia -viral"),
+            ("record_type", "discharge-summary"),
+            ("_count", "10"),
+        ]))
+        .unwrap();
+        let query = search.query();
+        let sql = query.sql().split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(sql.contains("websearch_to_tsquery('english', $1) AS query)"));
+        assert!(sql.contains("AND search_vector @@ search.query AND record_type = $2"));
+        assert!(sql.ends_with(
+            "ORDER BY ts_rank_cd(search_vector, search.query) DESC NULLS LAST, created_at DESC NULLS LAST, id LIMIT $3"
+        ));
+    }
+}
@@ -1,22 +1,22 @@
-/// Medical Record SQL Queries
-/// 
-/// This file contains all SQL queries used by the medical record service
-/// for clean separation of concerns and better maintainability.
+//! Medical Record SQL Queries
+//! 
+//! This file contains all SQL queries used by the medical record service
+//! for clean separation of concerns and better maintainability.
 
 /// Insert a new medical record
 pub const INSERT_MEDICAL_RECORD: &str = r#"
     INSERT INTO medical_records (
         id, patient_id, encounter_id, record_type, status, subject,
-        author, content, created_at, updated_at, meta
+        author, content, created_at, updated_at, meta, template_id, structured_data
     ) VALUES (
-        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13
     )
 "#;
 
 /// Get medical record by ID
 pub const GET_MEDICAL_RECORD_BY_ID: &str = r#"
     SELECT id, patient_id, encounter_id, record_type, status, subject,
-           author, content, created_at, updated_at, meta
+           author, content, created_at, updated_at, meta, template_id, structured_data
     FROM medical_records 
     WHERE id = $1 AND deleted_at IS NULL
 "#;
@@ -24,7 +24,7 @@ pub const GET_MEDICAL_RECORD_BY_ID: &str = r#"
 /// Get medical records by patient ID
 pub const GET_MEDICAL_RECORDS_BY_PATIENT: &str = r#"
     SELECT id, patient_id, encounter_id, record_type, status, subject,
-           author, content, created_at, updated_at, meta
+           author, content, created_at, updated_at, meta, template_id, structured_data
     FROM medical_records 
     WHERE patient_id = $1 AND deleted_at IS NULL
     ORDER BY created_at DESC
@@ -33,7 +33,9 @@ pub const GET_MEDICAL_RECORDS_BY_PATIENT: &str = r#"
 /// Soft delete medical record
 pub const SOFT_DELETE_MEDICAL_RECORD: &str = r#"
     UPDATE medical_records 
-    SET deleted_at = $1 
+    SET deleted_at = $1, meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
     WHERE id = $2 AND deleted_at IS NULL
 "#;
 
@@ -44,15 +46,130 @@ pub const UPDATE_MEDICAL_RECORD: &str = r#"
     WHERE id = $4 AND deleted_at IS NULL
 "#;
 
-/// Search medical records with filters
+/// Get the current version of a medical record
+pub const GET_MEDICAL_RECORD_VERSION: &str = r#"
+    SELECT COALESCE(meta->>'version_id', '1')
+    FROM medical_records 
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Get the status of a medical record
+pub const GET_MEDICAL_RECORD_STATUS: &str = r#"
+    SELECT status
+    FROM medical_records 
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Replace the content of a preliminary medical record if it is still at
+/// version $4, moving it to version $3
+pub const UPDATE_MEDICAL_RECORD_CONTENT: &str = r#"
+    UPDATE medical_records 
+    SET content = $1, updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($3::text))
+    WHERE id = $2 AND deleted_at IS NULL AND status = 'preliminary'
+      AND COALESCE(meta->>'version_id', '1') = $4
+"#;
+
+/// Get the template and structured data of a medical record
+pub const GET_MEDICAL_RECORD_STRUCTURED_DATA: &str = r#"
+    SELECT template_id, structured_data
+    FROM medical_records 
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Replace the structured data of a preliminary medical record, and the
+/// content rendered from it, if it is still at version $5, moving it to
+/// version $4
+pub const UPDATE_MEDICAL_RECORD_STRUCTURED_DATA: &str = r#"
+    UPDATE medical_records 
+    SET structured_data = $1, content = $2, updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($4::text))
+    WHERE id = $3 AND deleted_at IS NULL AND status = 'preliminary'
+      AND COALESCE(meta->>'version_id', '1') = $5
+"#;
+
+/// Finalize medical record if it is still at version $3, moving it to
+/// version $2
+pub const FINALIZE_MEDICAL_RECORD: &str = r#"
+    UPDATE medical_records 
+    SET status = 'final', updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($2::text))
+    WHERE id = $1 AND deleted_at IS NULL AND COALESCE(meta->>'version_id', '1') = $3
+"#;
+
+/// Lock a medical record against concurrent amendment and get its status,
+/// version and content
+pub const LOCK_MEDICAL_RECORD_FOR_AMENDMENT: &str = r#"
+    SELECT status, COALESCE(meta->>'version_id', '1') AS version_id, content
+    FROM medical_records 
+    WHERE id = $1 AND deleted_at IS NULL
+    FOR UPDATE
+"#;
+
+/// Mark a medical record amended, moving it to version $2
+pub const MARK_MEDICAL_RECORD_AMENDED: &str = r#"
+    UPDATE medical_records 
+    SET status = 'amended', updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($2::text))
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Record an addendum to a medical record
+pub const INSERT_AMENDMENT: &str = r#"
+    INSERT INTO medical_record_amendments (
+        id, medical_record_id, sequence, reason, text, struck_text,
+        strikes_amendment_id, version_id, amended_by
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9
+    )
+    RETURNING id, medical_record_id, sequence, reason, text, struck_text,
+              strikes_amendment_id, version_id, amended_by, amended_at
+"#;
+
+/// A medical record's addenda, in the order they were made
+pub const LIST_AMENDMENTS: &str = r#"
+    SELECT id, medical_record_id, sequence, reason, text, struck_text,
+           strikes_amendment_id, version_id, amended_by, amended_at
+    FROM medical_record_amendments
+    WHERE medical_record_id = $1
+    ORDER BY sequence
+"#;
+
+/// Start of a medical record search, newest first; the service appends
+/// the filters and the page's cursor, order and limit
 pub const SEARCH_MEDICAL_RECORDS: &str = r#"
     SELECT id, patient_id, encounter_id, record_type, status, subject,
-           author, content, created_at, updated_at, meta
+           author, content, created_at, updated_at, meta, template_id, structured_data,
+           ARRAY[created_at::text, id::text] AS page_key
     FROM medical_records 
     WHERE deleted_at IS NULL
-        AND ($1::uuid IS NULL OR patient_id = $1)
-        AND ($2::text IS NULL OR record_type ILIKE '%' || $2 || '%')
-        AND ($3::text IS NULL OR status ILIKE '%' || $3 || '%')
-    ORDER BY created_at DESC
-    LIMIT $4 OFFSET $5
-"#;
\ No newline at end of file
+"#;
+
+/// Start of an accurate medical record search total
+pub const COUNT_MEDICAL_RECORDS: &str = r#"
+    SELECT COUNT(*) FROM medical_records WHERE deleted_at IS NULL
+"#;
+
+/// Query plan of a medical record search, whose row estimate answers
+/// `_total=estimate`
+pub const ESTIMATE_MEDICAL_RECORDS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM medical_records WHERE deleted_at IS NULL
+"#;
+/// Start of a full-text search; the search text is bound next, and parsed
+/// as a web search (`"quoted phrase"`, `or`, `-excluded`)
+pub const TEXT_SEARCH_QUERY: &str = r#"
+    WITH search AS (SELECT websearch_to_tsquery('english', "#;
+
+/// Records matching the full-text query with their rank and highlighted
+/// passages; callers append `AND` filters, the page cursor, order and limit
+pub const TEXT_SEARCH_MEDICAL_RECORDS: &str = r#") AS query)
+    SELECT id, patient_id, encounter_id, record_type, status, subject,
+           author, content, created_at, updated_at, meta, template_id, structured_data,
+           ts_rank_cd(search_vector, search.query) AS score,
+           ts_headline('english', content, search.query,
+                       'StartSel=<mark>, StopSel=</mark>, MaxFragments=3, MinWords=5, MaxWords=25') AS highlight,
+           ARRAY[ts_rank_cd(search_vector, search.query)::text, created_at::text, id::text] AS page_key
+    FROM medical_records, search
+    WHERE deleted_at IS NULL
+      AND search_vector @@ search.query
+"#;
@@ -4,6 +4,9 @@
 //! - Electronic Health Records (EHR)
 //! - FHIR R4 compliance
 //! - Clinical document management
+//! - Amendment of finalized records by addenda, with the full amendment chain
+//! - Structured data captured with note templates
+//! - Ranked full-text search of document content
 //! - Medical history tracking
 //! - Audit logging
 
@@ -13,14 +16,24 @@ pub mod medical_record_controller;
 pub mod medical_record_service;
 #[path = "medical_record.sql.rs"]
 pub mod medical_record_sql;
+#[path = "medical_record.amendment.rs"]
+pub mod medical_record_amendment;
 
 pub use medical_record_controller::MedicalRecordController;
-pub use medical_record_service::MedicalRecordService;
+pub use medical_record_amendment::{Amendment, AmendmentChain, NewAmendment, Strike};
+pub use medical_record_service::{
+    MedicalRecordSearch, MedicalRecordSearchResult, MedicalRecordService, MedicalRecordTextHit, MedicalRecordTextSearch,
+    MedicalRecordTextSearchResult,
+};
 
-use axum::Router;
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::events::EventBus;
+use crate::standards::fhir::PackValidator;
+use crate::utils::api_router::ApiRouter;
+
 /// Medical Record Module Configuration
 pub struct MedicalRecordModule {
     pub service: Arc<MedicalRecordService>,
@@ -28,10 +41,16 @@ pub struct MedicalRecordModule {
 }
 
 impl MedicalRecordModule {
-    /// Create a new Medical Record Module with dependency injection
-    pub fn new(db_pool: PgPool) -> Self {
-        let service = Arc::new(MedicalRecordService::new(db_pool));
-        let controller = Arc::new(MedicalRecordController::new(service.clone()));
+    /// Create a new Medical Record Module with dependency injection;
+    /// records are validated against `profiles` when given
+    pub fn new(
+        db_pool: PgPool,
+        events: EventBus,
+        profiles: Option<PackValidator>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        let service = Arc::new(MedicalRecordService::with_events(db_pool, events).with_profiles(profiles));
+        let controller = Arc::new(MedicalRecordController::new(service.clone(), authorization_engine));
         
         Self {
             service,
@@ -40,7 +59,7 @@ impl MedicalRecordModule {
     }
 
     /// Register routes for this module
-    pub fn routes(&self) -> Router {
+    pub fn routes(&self) -> ApiRouter {
         self.controller.routes()
     }
 
@@ -0,0 +1,38 @@
+use axum::{extract::State, response::Json};
+use std::sync::Arc;
+use utoipa::openapi::OpenApi;
+
+use crate::modules::metadata::{CapabilityStatement, MetadataService};
+use crate::utils::api_router::ApiRouter;
+
+/// Metadata controller serving the CapabilityStatement and OpenAPI document
+pub struct MetadataController {
+    metadata_service: Arc<MetadataService>,
+}
+
+impl MetadataController {
+    /// Create new controller with injected service
+    pub fn new(metadata_service: Arc<MetadataService>) -> Self {
+        Self { metadata_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/metadata", Self::capability_statement, "FHIR CapabilityStatement of this server")
+            .get("/openapi.json", Self::openapi, "OpenAPI document for the non-FHIR endpoints")
+            .with_state(self.metadata_service.clone())
+    }
+
+    /// FHIR CapabilityStatement of this server
+    pub async fn capability_statement(
+        State(metadata_service): State<Arc<MetadataService>>,
+    ) -> Json<CapabilityStatement> {
+        Json(metadata_service.capability_statement().clone())
+    }
+
+    /// OpenAPI document for the non-FHIR endpoints
+    pub async fn openapi(State(metadata_service): State<Arc<MetadataService>>) -> Json<OpenApi> {
+        Json(metadata_service.openapi().clone())
+    }
+}
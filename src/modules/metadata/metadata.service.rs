@@ -0,0 +1,292 @@
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::openapi::info::InfoBuilder;
+use utoipa::openapi::path::{HttpMethod, OperationBuilder, ParameterBuilder, ParameterIn, PathItem, PathsBuilder};
+use utoipa::openapi::response::ResponseBuilder;
+use utoipa::openapi::schema::{ObjectBuilder, Type};
+use utoipa::openapi::{OpenApi, OpenApiBuilder, Required};
+
+use crate::utils::api_router::ApiRoute;
+use crate::utils::fhir_search::SearchParamDefinition;
+
+/// FHIR version the REST layer implements
+pub const FHIR_VERSION: &str = "4.0.1";
+
+/// A FHIR resource type served under a path. Interactions are read off the
+/// routes mounted there; the rest is declared by the resource's module.
+#[derive(Debug, Clone)]
+pub struct FhirResource {
+    pub resource_type: &'static str,
+    /// Mount point, e.g. `/api/v1/patients`
+    pub path: &'static str,
+    pub search_params: &'static [SearchParamDefinition],
+    pub search_include: &'static [&'static str],
+    pub search_rev_include: &'static [&'static str],
+    /// Whether `POST` honours `If-None-Exist`
+    pub conditional_create: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityStatement {
+    pub resource_type: &'static str,
+    pub status: &'static str,
+    pub date: DateTime<Utc>,
+    pub kind: &'static str,
+    pub software: CapabilitySoftware,
+    pub fhir_version: &'static str,
+    pub format: Vec<&'static str>,
+    pub rest: Vec<CapabilityRest>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilitySoftware {
+    pub name: &'static str,
+    pub version: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityRest {
+    pub mode: &'static str,
+    pub resource: Vec<CapabilityResource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityResource {
+    #[serde(rename = "type")]
+    pub resource_type: &'static str,
+    pub interaction: Vec<CapabilityInteraction>,
+    pub versioning: &'static str,
+    pub read_history: bool,
+    pub update_create: bool,
+    pub conditional_create: bool,
+    pub conditional_update: bool,
+    pub conditional_delete: &'static str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub search_include: Vec<&'static str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub search_rev_include: Vec<&'static str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub search_param: Vec<SearchParamDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CapabilityInteraction {
+    pub code: &'static str,
+}
+
+/// Builds the server's CapabilityStatement and OpenAPI document from the
+/// routes it mounts
+#[derive(Clone)]
+pub struct MetadataService {
+    capability_statement: CapabilityStatement,
+    openapi: OpenApi,
+}
+
+impl MetadataService {
+    pub fn new(routes: &[ApiRoute], resources: &[FhirResource]) -> Self {
+        Self {
+            capability_statement: Self::build_capability_statement(routes, resources),
+            openapi: Self::build_openapi(routes, resources),
+        }
+    }
+
+    pub fn capability_statement(&self) -> &CapabilityStatement {
+        &self.capability_statement
+    }
+
+    pub fn openapi(&self) -> &OpenApi {
+        &self.openapi
+    }
+
+    fn build_capability_statement(routes: &[ApiRoute], resources: &[FhirResource]) -> CapabilityStatement {
+        let resource = resources
+            .iter()
+            .map(|resource| {
+                let mounted: Vec<(&Method, &str)> = routes
+                    .iter()
+                    .filter_map(|route| Some((&route.method, resource_relative(&route.path, resource.path)?)))
+                    .collect();
+                let has = |method: Method, path: &str| mounted.iter().any(|(m, p)| **m == method && *p == path);
+
+                let interaction = [
+                    ("read", has(Method::GET, "/:id")),
+                    ("vread", has(Method::GET, "/:id/_history/:vid")),
+                    ("update", has(Method::PUT, "/:id")),
+                    ("patch", has(Method::PATCH, "/:id")),
+                    ("delete", has(Method::DELETE, "/:id")),
+                    ("history-instance", has(Method::GET, "/:id/_history")),
+                    ("create", has(Method::POST, "")),
+                    ("search-type", has(Method::GET, "")),
+                ]
+                .into_iter()
+                .filter(|(_, supported)| *supported)
+                .map(|(code, _)| CapabilityInteraction { code })
+                .collect();
+
+                let searchable = has(Method::GET, "");
+                CapabilityResource {
+                    resource_type: resource.resource_type,
+                    interaction,
+                    // Every update names the version it replaces with If-Match
+                    versioning: "versioned-update",
+                    read_history: has(Method::GET, "/:id/_history/:vid"),
+                    update_create: false,
+                    conditional_create: resource.conditional_create && has(Method::POST, ""),
+                    conditional_update: has(Method::PUT, ""),
+                    conditional_delete: if has(Method::DELETE, "") { "single" } else { "not-supported" },
+                    search_include: if searchable { resource.search_include.to_vec() } else { Vec::new() },
+                    search_rev_include: if searchable { resource.search_rev_include.to_vec() } else { Vec::new() },
+                    search_param: if searchable { resource.search_params.to_vec() } else { Vec::new() },
+                }
+            })
+            .collect();
+
+        CapabilityStatement {
+            resource_type: "CapabilityStatement",
+            status: "active",
+            date: Utc::now(),
+            kind: "instance",
+            software: CapabilitySoftware {
+                name: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+            },
+            fhir_version: FHIR_VERSION,
+            format: vec!["json"],
+            rest: vec![CapabilityRest {
+                mode: "server",
+                resource,
+            }],
+        }
+    }
+
+    /// OpenAPI for every route outside the FHIR resources, which the
+    /// CapabilityStatement describes instead
+    fn build_openapi(routes: &[ApiRoute], resources: &[FhirResource]) -> OpenApi {
+        let mut paths = PathsBuilder::new();
+        for route in routes {
+            if resources.iter().any(|resource| resource_relative(&route.path, resource.path).is_some()) {
+                continue;
+            }
+            let Some(method) = http_method(&route.method) else {
+                continue;
+            };
+
+            let mut operation = OperationBuilder::new()
+                .tag(tag_of(&route.path))
+                .summary(Some(route.summary))
+                .operation_id(Some(operation_id(route)))
+                .response("default", ResponseBuilder::new().description("Error as {error, message}").build());
+            for param in route.path_params() {
+                operation = operation.parameter(
+                    ParameterBuilder::new()
+                        .name(param)
+                        .parameter_in(ParameterIn::Path)
+                        .required(Required::True)
+                        .schema(Some(ObjectBuilder::new().schema_type(Type::String))),
+                );
+            }
+            let operation = operation.response("200", ResponseBuilder::new().description("Success").build());
+            paths = paths.path(route.openapi_path(), PathItem::new(method, operation.build()));
+        }
+
+        OpenApiBuilder::new()
+            .info(
+                InfoBuilder::new()
+                    .title("HIMS API")
+                    .version(env!("CARGO_PKG_VERSION"))
+                    .description(Some("Non-FHIR endpoints. FHIR resources are described by GET /metadata.")),
+            )
+            .paths(paths.build())
+            .build()
+    }
+}
+
+/// `path` relative to a resource mount point, or `None` if it lies outside
+fn resource_relative<'a>(path: &'a str, mount: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(mount)?;
+    (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+}
+
+fn http_method(method: &Method) -> Option<HttpMethod> {
+    match *method {
+        Method::GET => Some(HttpMethod::Get),
+        Method::POST => Some(HttpMethod::Post),
+        Method::PUT => Some(HttpMethod::Put),
+        Method::PATCH => Some(HttpMethod::Patch),
+        Method::DELETE => Some(HttpMethod::Delete),
+        _ => None,
+    }
+}
+
+/// Group routes by the segment after `/api/v1`; root routes are operational
+fn tag_of(path: &str) -> String {
+    match path.strip_prefix("/api/v1/") {
+        Some(rest) => rest.split('/').next().unwrap_or(rest).to_string(),
+        None => "operations".to_string(),
+    }
+}
+
+/// `get_api_v1_roles_id_members` for `GET /api/v1/roles/:id/members`
+fn operation_id(route: &ApiRoute) -> String {
+    let path: Vec<&str> = route
+        .path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.trim_start_matches(':'))
+        .collect();
+    format!("{}_{}", route.method.as_str().to_lowercase(), path.join("_").replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(method: Method, path: &str) -> ApiRoute {
+        ApiRoute {
+            method,
+            path: path.to_string(),
+            summary: "test",
+        }
+    }
+
+    #[test]
+    fn test_interactions_follow_routes() {
+        let routes = vec![
+            route(Method::GET, "/api/v1/patients"),
+            route(Method::PUT, "/api/v1/patients"),
+            route(Method::GET, "/api/v1/patients/:id"),
+            route(Method::GET, "/api/v1/patients/:id/_history/:vid"),
+            route(Method::POST, "/api/v1/patients-archive"),
+            route(Method::GET, "/api/v1/roles/:id"),
+        ];
+        let resources = [FhirResource {
+            resource_type: "Patient",
+            path: "/api/v1/patients",
+            search_params: &[],
+            search_include: &[],
+            search_rev_include: &["Appointment:patient"],
+            conditional_create: true,
+        }];
+
+        let service = MetadataService::new(&routes, &resources);
+        let patient = &service.capability_statement().rest[0].resource[0];
+        let codes: Vec<&str> = patient.interaction.iter().map(|i| i.code).collect();
+        assert_eq!(codes, vec!["read", "vread", "search-type"]);
+        assert!(patient.read_history);
+        assert!(patient.conditional_update);
+        // No POST on the resource itself, so no conditional create
+        assert!(!patient.conditional_create);
+        assert_eq!(patient.conditional_delete, "not-supported");
+        assert_eq!(patient.search_rev_include, vec!["Appointment:patient"]);
+
+        let openapi = serde_json::to_value(service.openapi()).unwrap();
+        let paths = openapi["paths"].as_object().unwrap();
+        assert!(paths.contains_key("/api/v1/roles/{id}"));
+        assert!(paths.contains_key("/api/v1/patients-archive"));
+        assert!(!paths.keys().any(|path| path.starts_with("/api/v1/patients/")));
+        assert_eq!(openapi["paths"]["/api/v1/roles/{id}"]["get"]["tags"][0], "roles");
+    }
+}
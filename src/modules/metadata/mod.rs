@@ -0,0 +1,50 @@
+//! Metadata Module
+//! 
+//! This module describes the REST layer to clients including:
+//! - FHIR CapabilityStatement at `GET /metadata`, listing the resources, interactions and search parameters actually served
+//! - OpenAPI 3.1 document at `GET /openapi.json` for the non-FHIR endpoints
+//! - Both generated from the routes recorded by `ApiRouter`, so they cannot drift from what is mounted
+
+#[path = "metadata.controller.rs"]
+pub mod metadata_controller;
+#[path = "metadata.service.rs"]
+pub mod metadata_service;
+
+pub use metadata_controller::MetadataController;
+pub use metadata_service::{
+    CapabilityInteraction, CapabilityResource, CapabilityRest, CapabilitySoftware, CapabilityStatement,
+    FhirResource, MetadataService, FHIR_VERSION,
+};
+
+use std::sync::Arc;
+
+use crate::utils::api_router::{ApiRoute, ApiRouter};
+
+/// Metadata Module Configuration
+pub struct MetadataModule {
+    pub service: Arc<MetadataService>,
+    pub controller: Arc<MetadataController>,
+}
+
+impl MetadataModule {
+    /// Create a new Metadata Module describing the given routes
+    pub fn new(routes: &[ApiRoute], resources: &[FhirResource]) -> Self {
+        let service = Arc::new(MetadataService::new(routes, resources));
+        let controller = Arc::new(MetadataController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register metadata routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<MetadataService> {
+        self.service.clone()
+    }
+}
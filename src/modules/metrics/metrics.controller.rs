@@ -0,0 +1,83 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::modules::metrics::MetricsService;
+use crate::utils::api_router::ApiRouter;
+
+/// Content type of the Prometheus text exposition format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Metrics controller serving the scrape endpoint and Kubernetes probes
+pub struct MetricsController {
+    metrics_service: Arc<MetricsService>,
+}
+
+impl MetricsController {
+    /// Create new controller with injected service
+    pub fn new(metrics_service: Arc<MetricsService>) -> Self {
+        Self { metrics_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/metrics", Self::metrics, "Prometheus scrape endpoint")
+            .get("/healthz", Self::healthz, "Liveness probe: the process is up and serving requests")
+            .get("/readyz", Self::readyz, "Readiness probe: the database is reachable")
+            .with_state(self.metrics_service.clone())
+    }
+
+    /// Prometheus scrape endpoint
+    pub async fn metrics(State(metrics_service): State<Arc<MetricsService>>) -> Response {
+        (
+            [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+            metrics_service.render(),
+        )
+            .into_response()
+    }
+
+    /// Liveness probe: the process is up and serving requests
+    pub async fn healthz() -> (StatusCode, &'static str) {
+        (StatusCode::OK, "ok")
+    }
+
+    /// Readiness probe: the database is reachable
+    pub async fn readyz(State(metrics_service): State<Arc<MetricsService>>) -> (StatusCode, String) {
+        match metrics_service.readiness().await {
+            Ok(()) => (StatusCode::OK, "ready".to_string()),
+            Err(e) => {
+                tracing::warn!("Readiness check failed: {}", e);
+                (StatusCode::SERVICE_UNAVAILABLE, "not ready".to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::events::{DomainEvent, EventBus};
+    use sqlx::PgPool;
+
+    #[tokio::test]
+    async fn test_metrics_reports_queue_depths() {
+        let pool = PgPool::connect_lazy("postgres://localhost/hims").unwrap();
+        let metrics_service = Arc::new(MetricsService::new(pool));
+        let events = EventBus::default();
+        // Held by a subscriber that has not read it yet
+        let _subscriber = events.subscribe();
+        events.publish(DomainEvent::PatientCreated { patient_id: uuid::Uuid::new_v4() });
+        metrics_service.register_queue("events", Arc::new(events));
+        metrics_service.refresh_queue_depths().await;
+
+        let response = MetricsController::metrics(State(metrics_service)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("hims_queue_depth{queue=\"events\"} 1"), "{}", body);
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::modules::metrics::MetricsService;
+
+/// Request metrics middleware
+pub struct MetricsMiddleware {
+    metrics_service: Arc<MetricsService>,
+}
+
+impl MetricsMiddleware {
+    pub fn new(metrics_service: Arc<MetricsService>) -> Self {
+        Self { metrics_service }
+    }
+
+    pub fn service(&self) -> Arc<MetricsService> {
+        self.metrics_service.clone()
+    }
+
+    /// Count the request and time it under its route template, e.g.
+    /// `/api/v1/patients/:id`, so IDs never become label values
+    pub async fn track(
+        State(metrics_service): State<Arc<MetricsService>>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let method = request.method().to_string();
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+
+        let started = Instant::now();
+        let response = next.run(request).await;
+        metrics_service.record_request(&method, &route, response.status().as_u16(), started.elapsed());
+        response
+    }
+}
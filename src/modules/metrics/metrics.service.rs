@@ -0,0 +1,334 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::modules::authorization::relation_cache_stats;
+
+/// Upper bounds, in seconds, of the request latency histogram buckets
+pub const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// How long the readiness probe waits for the database
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often queue depths are read for `hims_queue_depth`
+pub const QUEUE_DEPTH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A queue whose depth is exposed as `hims_queue_depth`
+#[async_trait]
+pub trait QueueDepth: Send + Sync {
+    /// Messages waiting in the queue, across tenants
+    async fn depth(&self) -> Result<i64, HimsError>;
+}
+
+#[derive(Debug, Default, Clone)]
+struct LatencyHistogram {
+    /// Cumulative count per bucket, aligned with `LATENCY_BUCKETS`
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl LatencyHistogram {
+    fn observe(&mut self, seconds: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct RequestMetrics {
+    /// (method, route, status) -> count
+    counts: BTreeMap<(String, String, u16), u64>,
+    /// (method, route) -> latency
+    latencies: BTreeMap<(String, String), LatencyHistogram>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct MaintenanceMetrics {
+    runs: u64,
+    rows_affected: u64,
+    last_rows_affected: u64,
+}
+
+/// Collects request metrics and renders them with runtime gauges in the
+/// Prometheus text exposition format
+pub struct MetricsService {
+    db_pool: PgPool,
+    requests: Mutex<RequestMetrics>,
+    queues: RwLock<BTreeMap<String, Arc<dyn QueueDepth>>>,
+    /// queue -> depth when last read; scrapes report these rather than
+    /// querying each queue
+    queue_depths: RwLock<BTreeMap<String, i64>>,
+    /// task -> runs and rows affected
+    maintenance: Mutex<BTreeMap<String, MaintenanceMetrics>>,
+    started_at: Instant,
+}
+
+impl MetricsService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            requests: Mutex::new(RequestMetrics::default()),
+            queues: RwLock::new(BTreeMap::new()),
+            queue_depths: RwLock::new(BTreeMap::new()),
+            maintenance: Mutex::new(BTreeMap::new()),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Record a finished request. `route` is the matched route template,
+    /// not the raw path, to keep label cardinality bounded.
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let mut requests = self.requests.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *requests
+            .counts
+            .entry((method.to_string(), route.to_string(), status))
+            .or_insert(0) += 1;
+        requests
+            .latencies
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Expose the depth of a queue as `hims_queue_depth{queue="<name>"}`,
+    /// from the first read of queue depths on
+    pub fn register_queue(&self, name: &str, queue: Arc<dyn QueueDepth>) {
+        self.queues
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(name.to_string(), queue);
+    }
+
+    /// Read the depth of every registered queue. A queue that cannot be
+    /// read keeps the depth it last had.
+    pub async fn refresh_queue_depths(&self) {
+        let queues: Vec<(String, Arc<dyn QueueDepth>)> = self
+            .queues
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|(name, queue)| (name.clone(), queue.clone()))
+            .collect();
+
+        for (name, queue) in queues {
+            match queue.depth().await {
+                Ok(depth) => {
+                    self.queue_depths
+                        .write()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .insert(name, depth);
+                }
+                Err(e) => tracing::warn!("Failed to read depth of queue {}: {}", name, e),
+            }
+        }
+    }
+
+    /// Read queue depths every `interval`, across tenants
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.refresh_queue_depths().await;
+            }
+        }))
+    }
+
+    /// Record a run of a maintenance task and the rows it changed
+    pub fn record_maintenance(&self, task: &str, rows_affected: u64) {
+        let mut maintenance = self.maintenance.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let metrics = maintenance.entry(task.to_string()).or_default();
+        metrics.runs += 1;
+        metrics.rows_affected += rows_affected;
+        metrics.last_rows_affected = rows_affected;
+    }
+
+    /// Whether the service can take traffic: the database answers in time
+    pub async fn readiness(&self) -> Result<(), HimsError> {
+        if self.db_pool.is_closed() {
+            return Err(HimsError::DatabaseError("Connection pool is closed".to_string()));
+        }
+        match tokio::time::timeout(READINESS_TIMEOUT, sqlx::query("SELECT 1").execute(&self.db_pool)).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(HimsError::DatabaseError(e.to_string())),
+            Err(_) => Err(HimsError::DatabaseError("Database did not respond in time".to_string())),
+        }
+    }
+
+    /// Render all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        gauge(&mut out, "hims_uptime_seconds", "Seconds since the service started", self.started_at.elapsed().as_secs_f64());
+
+        let size = self.db_pool.size() as f64;
+        let idle = self.db_pool.num_idle() as f64;
+        header(&mut out, "hims_db_pool_connections", "gauge", "Database pool connections by state");
+        let _ = writeln!(out, "hims_db_pool_connections{{state=\"active\"}} {}", size - idle);
+        let _ = writeln!(out, "hims_db_pool_connections{{state=\"idle\"}} {}", idle);
+
+        let (hits, misses) = relation_cache_stats();
+        counter(&mut out, "hims_authz_relation_cache_hits_total", "Authorization relation cache hits", hits);
+        counter(&mut out, "hims_authz_relation_cache_misses_total", "Authorization relation cache misses", misses);
+        let lookups = hits + misses;
+        let hit_ratio = if lookups == 0 { 0.0 } else { hits as f64 / lookups as f64 };
+        gauge(&mut out, "hims_authz_relation_cache_hit_ratio", "Share of relation lookups served from cache", hit_ratio);
+
+        let queue_depths = self.queue_depths.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !queue_depths.is_empty() {
+            header(&mut out, "hims_queue_depth", "gauge", "Messages waiting per queue");
+            for (name, depth) in queue_depths.iter() {
+                let _ = writeln!(out, "hims_queue_depth{{queue=\"{}\"}} {}", escape(name), depth);
+            }
+        }
+        drop(queue_depths);
+
+        let maintenance = self.maintenance.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !maintenance.is_empty() {
+            header(&mut out, "hims_maintenance_runs_total", "counter", "Maintenance task runs on this server");
+            for (task, metrics) in maintenance.iter() {
+                let _ = writeln!(out, "hims_maintenance_runs_total{{task=\"{}\"}} {}", escape(task), metrics.runs);
+            }
+            header(&mut out, "hims_maintenance_rows_affected_total", "counter", "Rows changed by maintenance tasks");
+            for (task, metrics) in maintenance.iter() {
+                let _ = writeln!(
+                    out,
+                    "hims_maintenance_rows_affected_total{{task=\"{}\"}} {}",
+                    escape(task),
+                    metrics.rows_affected
+                );
+            }
+            header(&mut out, "hims_maintenance_last_rows_affected", "gauge", "Rows changed by a task's latest run");
+            for (task, metrics) in maintenance.iter() {
+                let _ = writeln!(
+                    out,
+                    "hims_maintenance_last_rows_affected{{task=\"{}\"}} {}",
+                    escape(task),
+                    metrics.last_rows_affected
+                );
+            }
+        }
+        drop(maintenance);
+
+        let requests = self.requests.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        header(&mut out, "hims_http_requests_total", "counter", "HTTP requests by route and status");
+        for ((method, route, status), count) in &requests.counts {
+            let _ = writeln!(
+                out,
+                "hims_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                method,
+                escape(route),
+                status,
+                count
+            );
+        }
+
+        header(&mut out, "hims_http_request_duration_seconds", "histogram", "HTTP request latency by route");
+        for ((method, route), histogram) in &requests.latencies {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, escape(route));
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+                let _ = writeln!(out, "hims_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound, count);
+            }
+            let _ = writeln!(out, "hims_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, histogram.count);
+            let _ = writeln!(out, "hims_http_request_duration_seconds_sum{{{}}} {}", labels, histogram.sum);
+            let _ = writeln!(out, "hims_http_request_duration_seconds_count{{{}}} {}", labels, histogram.count);
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, "gauge", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, "counter", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.observe(0.02);
+        histogram.observe(0.3);
+        histogram.observe(20.0);
+
+        // 0.005, 0.01 | 0.025 .. 0.25 | 0.5 .. 10
+        assert_eq!(histogram.buckets[0], 0);
+        assert_eq!(histogram.buckets[2], 1);
+        assert_eq!(histogram.buckets[6], 2);
+        assert_eq!(*histogram.buckets.last().unwrap(), 2);
+        assert_eq!(histogram.count, 3);
+    }
+
+    struct Backlog(i64);
+
+    #[async_trait]
+    impl QueueDepth for Backlog {
+        async fn depth(&self) -> Result<i64, HimsError> {
+            Ok(self.0)
+        }
+    }
+
+    struct Unreachable;
+
+    #[async_trait]
+    impl QueueDepth for Unreachable {
+        async fn depth(&self) -> Result<i64, HimsError> {
+            Err(HimsError::DatabaseError("connection refused".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn queue_depths_are_scraped_as_read() {
+        let pool = PgPool::connect_lazy("postgres://localhost/hims").unwrap();
+        let service = MetricsService::new(pool);
+        service.register_queue("webhook", Arc::new(Backlog(3)));
+        service.register_queue("interface_engine", Arc::new(Unreachable));
+        // Nothing is reported until the depths are first read
+        assert!(!service.render().contains("hims_queue_depth"));
+
+        service.refresh_queue_depths().await;
+        let out = service.render();
+        assert!(out.contains("# TYPE hims_queue_depth gauge"));
+        assert!(out.contains("hims_queue_depth{queue=\"webhook\"} 3"));
+        // An unreadable queue is left out rather than reported empty
+        assert!(!out.contains("queue=\"interface_engine\""));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
@@ -0,0 +1,55 @@
+//! Metrics Module
+//! 
+//! This module provides operational endpoints for Kubernetes deployments including:
+//! - Prometheus `/metrics` with per-endpoint request counters and latency histograms
+//! - Database pool, authorization cache and queue depth gauges
+//! - Runs of maintenance tasks and the rows they changed
+//! - `/healthz` liveness and `/readyz` readiness probes
+
+#[path = "metrics.controller.rs"]
+pub mod metrics_controller;
+#[path = "metrics.service.rs"]
+pub mod metrics_service;
+#[path = "metrics.middleware.rs"]
+pub mod metrics_middleware;
+
+pub use metrics_controller::MetricsController;
+pub use metrics_service::{MetricsService, QueueDepth, QUEUE_DEPTH_INTERVAL};
+pub use metrics_middleware::MetricsMiddleware;
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Metrics Module Configuration
+pub struct MetricsModule {
+    pub service: Arc<MetricsService>,
+    pub controller: Arc<MetricsController>,
+    pub middleware: Arc<MetricsMiddleware>,
+}
+
+impl MetricsModule {
+    /// Create a new Metrics Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(MetricsService::new(db_pool));
+        let controller = Arc::new(MetricsController::new(service.clone()));
+        let middleware = Arc::new(MetricsMiddleware::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+            middleware,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<MetricsService> {
+        self.service.clone()
+    }
+}
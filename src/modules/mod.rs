@@ -9,17 +9,122 @@ pub mod medical_record;
 pub mod audit;
 pub mod auth;
 pub mod authorization;
+pub mod role;
+pub mod delegation;
+pub mod service_account;
+pub mod tenant;
+pub mod metrics;
+pub mod history;
+pub mod metadata;
+pub mod events;
+pub mod subscription;
+pub mod interface_engine;
+pub mod adt_feed;
+pub mod integration;
+pub mod webhook;
+pub mod graphql;
+pub mod rate_limit;
+pub mod idempotency;
+pub mod attachment;
+pub mod note_template;
+pub mod condition;
+pub mod vital_sign;
+pub mod device_gateway;
+pub mod task;
+pub mod care_plan;
+pub mod referral;
+pub mod inventory;
+pub mod theatre;
+pub mod emergency;
+pub mod radiology;
+pub mod vital_record;
+pub mod surveillance;
+pub mod billing;
+pub mod report;
+pub mod analytics;
+pub mod omop;
+pub mod dataset;
+pub mod cohort;
+pub mod quality;
+pub mod immunization;
+pub mod telemedicine;
+pub mod identity_verification;
+pub mod scheduler;
+pub mod maintenance;
+pub mod soft_delete;
+pub mod provenance;
+pub mod field_filter;
+pub mod security_label;
+pub mod part2;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "sqlite")]
+pub mod sync;
 
 pub use patient::PatientModule;
 pub use appointment::AppointmentModule;
 pub use medical_record::MedicalRecordModule;
 pub use audit::AuditModule;
 pub use auth::AuthModule;
+pub use role::RoleModule;
+pub use delegation::DelegationModule;
+pub use service_account::ServiceAccountModule;
+pub use tenant::{TenantMiddleware, TenantModule};
+pub use metrics::{MetricsMiddleware, MetricsModule};
+pub use history::{HistoryMiddleware, HistoryModule};
+pub use metadata::{FhirResource, MetadataModule};
+pub use subscription::SubscriptionModule;
+pub use interface_engine::InterfaceEngineModule;
+pub use adt_feed::AdtFeedModule;
+pub use integration::IntegrationModule;
+pub use webhook::WebhookModule;
+pub use graphql::GraphqlModule;
+pub use rate_limit::{RateLimitConfig, RateLimitMiddleware, RateLimitModule};
+pub use idempotency::{IdempotencyMiddleware, IdempotencyModule};
+pub use attachment::{AttachmentConfig, AttachmentModule};
+pub use note_template::NoteTemplateModule;
+pub use condition::ConditionModule;
+pub use vital_sign::VitalSignModule;
+pub use device_gateway::{DeviceGatewayConfig, DeviceGatewayModule};
+pub use task::TaskModule;
+pub use care_plan::CarePlanModule;
+pub use referral::ReferralModule;
+pub use inventory::InventoryModule;
+pub use theatre::TheatreModule;
+pub use emergency::{EmergencyConfig, EmergencyModule};
+pub use radiology::RadiologyModule;
+pub use vital_record::{VitalRecordConfig, VitalRecordModule};
+pub use surveillance::{SurveillanceConfig, SurveillanceModule};
+pub use billing::{BillingModule, PmjayConfig};
+pub use report::ReportModule;
+pub use analytics::AnalyticsModule;
+pub use omop::OmopModule;
+pub use dataset::DatasetModule;
+pub use cohort::CohortModule;
+pub use quality::{QualityConfig, QualityModule};
+pub use immunization::{ImmunizationConfig, ImmunizationModule};
+pub use telemedicine::{TelemedicineConfig, TelemedicineModule};
+pub use identity_verification::{IdentityVerificationConfig, IdentityVerificationModule};
+pub use scheduler::{ScheduledJob, SchedulerConfig, SchedulerModule};
+pub use maintenance::MaintenanceModule;
+pub use soft_delete::{SoftDeleteConfig, SoftDeleteModule};
+pub use provenance::ProvenanceModule;
+pub use field_filter::{FieldFilterConfig, FieldFilterMiddleware, FieldFilterModule};
+pub use security_label::SecurityLabelModule;
+pub use part2::Part2Module;
+#[cfg(feature = "grpc")]
+pub use grpc::GrpcModule;
+pub use events::{DomainEvent, EventBus, EventsController};
 
 use axum::Router;
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::core::ConfigLoader;
+use crate::modules::authorization::{AuthorizationController, HimsAuthorizationEngine};
+use crate::standards::fhir::PackValidator;
+use crate::utils::api_router::ApiRouter;
+
 /// Application Module Registry
 /// 
 /// Central registry for all application modules with dependency injection
@@ -29,27 +134,508 @@ pub struct AppModules {
     pub medical_record: Arc<MedicalRecordModule>,
     pub audit: Arc<AuditModule>,
     pub auth: Arc<AuthModule>,
+    pub role: Arc<RoleModule>,
+    pub delegation: Arc<DelegationModule>,
+    pub service_account: Arc<ServiceAccountModule>,
+    pub tenant: Arc<TenantModule>,
+    pub metrics: Arc<MetricsModule>,
+    pub history: Arc<HistoryModule>,
+    pub subscription: Arc<SubscriptionModule>,
+    pub interface_engine: Arc<InterfaceEngineModule>,
+    pub adt_feed: Arc<AdtFeedModule>,
+    pub integration: Arc<IntegrationModule>,
+    pub webhook: Arc<WebhookModule>,
+    pub graphql: Arc<GraphqlModule>,
+    pub rate_limit: Arc<RateLimitModule>,
+    pub field_filter: Arc<FieldFilterModule>,
+    pub idempotency: Arc<IdempotencyModule>,
+    pub attachment: Arc<AttachmentModule>,
+    pub note_template: Arc<NoteTemplateModule>,
+    pub condition: Arc<ConditionModule>,
+    pub vital_sign: Arc<VitalSignModule>,
+    pub device_gateway: Arc<DeviceGatewayModule>,
+    pub task: Arc<TaskModule>,
+    pub care_plan: Arc<CarePlanModule>,
+    pub referral: Arc<ReferralModule>,
+    pub inventory: Arc<InventoryModule>,
+    pub theatre: Arc<TheatreModule>,
+    pub emergency: Arc<EmergencyModule>,
+    pub radiology: Arc<RadiologyModule>,
+    pub vital_record: Arc<VitalRecordModule>,
+    pub surveillance: Arc<SurveillanceModule>,
+    pub billing: Arc<BillingModule>,
+    pub report: Arc<ReportModule>,
+    pub analytics: Arc<AnalyticsModule>,
+    pub omop: Arc<OmopModule>,
+    pub dataset: Arc<DatasetModule>,
+    pub cohort: Arc<CohortModule>,
+    pub quality: Arc<QualityModule>,
+    pub immunization: Arc<ImmunizationModule>,
+    pub telemedicine: Arc<TelemedicineModule>,
+    pub identity_verification: Arc<IdentityVerificationModule>,
+    pub scheduler: Arc<SchedulerModule>,
+    pub maintenance: Arc<MaintenanceModule>,
+    pub soft_delete: Arc<SoftDeleteModule>,
+    pub provenance: Arc<ProvenanceModule>,
+    pub security_label: Arc<SecurityLabelModule>,
+    pub part2: Arc<Part2Module>,
+    #[cfg(feature = "grpc")]
+    pub grpc: Arc<GrpcModule>,
+    /// Domain events published by module writes
+    pub events: EventBus,
+    /// Engine deciding access to patient data
+    pub authorization: Arc<HimsAuthorizationEngine>,
 }
 
 impl AppModules {
     /// Initialize all application modules with shared dependencies
     pub fn new(db_pool: PgPool) -> Self {
+        let authorization = Arc::new(HimsAuthorizationEngine::postgres(db_pool.clone()));
+        Self::with_events(db_pool, EventBus::default(), authorization)
+    }
+
+    /// Initialize all application modules publishing to `events`, with
+    /// access to patient data decided by `authorization`
+    pub fn with_events(db_pool: PgPool, events: EventBus, authorization: Arc<HimsAuthorizationEngine>) -> Self {
+        let profiles = Self::profiles();
+        let interface_engine = Arc::new(InterfaceEngineModule::new(db_pool.clone()));
+        let adt_feed = Arc::new(AdtFeedModule::new(db_pool.clone()));
+        let integration = Arc::new(IntegrationModule::new(db_pool.clone(), events.clone()));
+        interface_engine.get_service().register("hl7", adt_feed.get_service());
+        interface_engine.get_service().register("channel", integration.get_service());
+        let patient = Arc::new(PatientModule::new(
+            db_pool.clone(),
+            events.clone(),
+            profiles.clone(),
+            authorization.clone(),
+        ));
+        let appointment = Arc::new(AppointmentModule::new(db_pool.clone(), events.clone(), authorization.clone()));
+        let graphql = Arc::new(GraphqlModule::new(
+            db_pool.clone(),
+            patient.get_service(),
+            appointment.get_service(),
+            authorization.clone(),
+        ));
+        #[cfg(feature = "grpc")]
+        let grpc = Arc::new(GrpcModule::new(db_pool.clone(), patient.get_service(), authorization.clone()));
+        let task = Arc::new(TaskModule::new(db_pool.clone(), events.clone(), authorization.clone()));
+        let care_plan = Arc::new(CarePlanModule::new(
+            db_pool.clone(),
+            events.clone(),
+            task.get_service(),
+            authorization.clone(),
+        ));
+        let condition = Arc::new(ConditionModule::new(db_pool.clone(), events.clone()));
+        let referral = Arc::new(ReferralModule::new(
+            db_pool.clone(),
+            events.clone(),
+            patient.get_service(),
+            condition.get_service(),
+            authorization.clone(),
+        ));
+        let vital_sign = Arc::new(VitalSignModule::new(db_pool.clone(), events.clone(), authorization.clone()));
+        let device_gateway = Arc::new(DeviceGatewayModule::new(
+            db_pool.clone(),
+            vital_sign.get_service(),
+            DeviceGatewayConfig::from_env(),
+        ));
+        let inventory = Arc::new(InventoryModule::new(db_pool.clone()));
+        let medical_record = Arc::new(MedicalRecordModule::new(
+            db_pool.clone(),
+            events.clone(),
+            profiles,
+            authorization.clone(),
+        ));
+        let theatre = Arc::new(TheatreModule::new(db_pool.clone(), medical_record.get_service()));
+        let emergency = Arc::new(EmergencyModule::new(db_pool.clone(), EmergencyConfig::from_env()));
+        let radiology = Arc::new(RadiologyModule::new(
+            db_pool.clone(),
+            patient.get_service(),
+            medical_record.get_service(),
+        ));
+        let vital_record = Arc::new(VitalRecordModule::new(
+            db_pool.clone(),
+            patient.get_service(),
+            VitalRecordConfig::from_env(),
+        ));
+        let surveillance = Arc::new(SurveillanceModule::new(
+            db_pool.clone(),
+            condition.get_service(),
+            patient.get_service(),
+            SurveillanceConfig::from_env(),
+        ));
+        let billing = Arc::new(BillingModule::new(
+            db_pool.clone(),
+            patient.get_service(),
+            PmjayConfig::from_env(),
+        ));
+        let report = Arc::new(ReportModule::new(db_pool.clone()));
+        let analytics = Arc::new(AnalyticsModule::new(db_pool.clone()));
+        let omop = Arc::new(OmopModule::new(db_pool.clone()));
+        let dataset = Arc::new(DatasetModule::new(db_pool.clone()));
+        let cohort = Arc::new(CohortModule::new(db_pool.clone()));
+        let quality = Arc::new(QualityModule::new(db_pool.clone(), QualityConfig::from_env()));
+        let immunization = Arc::new(ImmunizationModule::new(db_pool.clone(), ImmunizationConfig::from_env()));
+        let telemedicine = Arc::new(TelemedicineModule::new(
+            db_pool.clone(),
+            TelemedicineConfig::from_env(),
+            authorization.clone(),
+        ));
+        let identity_verification = Arc::new(IdentityVerificationModule::new(
+            db_pool.clone(),
+            IdentityVerificationConfig::from_env(),
+        ));
+        let idempotency = Arc::new(IdempotencyModule::new(db_pool.clone()));
+        let scheduler = Arc::new(SchedulerModule::new(db_pool.clone(), SchedulerConfig::from_env()));
+        let soft_delete = Arc::new(SoftDeleteModule::new(db_pool.clone(), SoftDeleteConfig::from_env()));
+        let jobs: [(&str, &str, &str, Arc<dyn ScheduledJob>); 3] = [
+            (
+                "idempotency-purge",
+                "Forget idempotency keys once their TTL has passed",
+                "0 * * * *",
+                idempotency.get_service(),
+            ),
+            (
+                "analytics-refresh",
+                "Recompute the dashboard's KPIs",
+                "*/15 * * * *",
+                analytics.get_service(),
+            ),
+            (
+                "purge-deleted-records",
+                "Hard-delete resources, with their history, deleted longer ago than retention",
+                "0 3 * * *",
+                soft_delete.get_service(),
+            ),
+        ];
+        for (name, description, schedule, job) in jobs {
+            if let Err(e) = scheduler.get_service().register(name, description, schedule, job) {
+                tracing::error!("Failed to register job {}: {}", name, e);
+            }
+        }
+        let auth = Arc::new(AuthModule::new(db_pool.clone()));
+        let webhook = Arc::new(WebhookModule::new(db_pool.clone()));
+        let metrics = Arc::new(MetricsModule::new(db_pool.clone()));
+        metrics.get_service().register_queue("interface_engine", interface_engine.get_service());
+        metrics.get_service().register_queue("webhook", webhook.get_service());
+        metrics.get_service().register_queue("events", Arc::new(events.clone()));
+        let maintenance = Arc::new(MaintenanceModule::new(
+            db_pool.clone(),
+            auth.get_service().sessions(),
+            metrics.get_service(),
+        ));
+        maintenance.schedule(&scheduler.get_service());
+
         Self {
-            patient: Arc::new(PatientModule::new(db_pool.clone())),
-            appointment: Arc::new(AppointmentModule::new(db_pool.clone())),
-            medical_record: Arc::new(MedicalRecordModule::new(db_pool.clone())),
+            patient,
+            appointment,
+            medical_record,
             audit: Arc::new(AuditModule::new(db_pool.clone())),
-            auth: Arc::new(AuthModule::new(db_pool)),
+            auth,
+            role: Arc::new(RoleModule::new(db_pool.clone())),
+            delegation: Arc::new(DelegationModule::new(db_pool.clone())),
+            service_account: Arc::new(ServiceAccountModule::new(db_pool.clone())),
+            tenant: Arc::new(TenantModule::new(db_pool.clone())),
+            metrics,
+            history: Arc::new(HistoryModule::new(db_pool.clone(), authorization.clone())),
+            provenance: Arc::new(ProvenanceModule::new(db_pool.clone())),
+            security_label: Arc::new(SecurityLabelModule::new(db_pool.clone())),
+            part2: Arc::new(Part2Module::new(db_pool.clone())),
+            subscription: Arc::new(SubscriptionModule::new(db_pool.clone())),
+            interface_engine,
+            adt_feed,
+            integration,
+            webhook,
+            graphql,
+            rate_limit: Arc::new(RateLimitModule::new(RateLimitConfig::from_env())),
+            field_filter: Arc::new(FieldFilterModule::new(db_pool.clone(), FieldFilterConfig::from_env())),
+            idempotency,
+            attachment: Arc::new(AttachmentModule::new(db_pool.clone(), AttachmentConfig::from_env(), authorization.clone())),
+            note_template: Arc::new(NoteTemplateModule::new(db_pool)),
+            condition,
+            vital_sign,
+            device_gateway,
+            task,
+            care_plan,
+            referral,
+            inventory,
+            theatre,
+            emergency,
+            radiology,
+            vital_record,
+            surveillance,
+            billing,
+            report,
+            analytics,
+            omop,
+            dataset,
+            cohort,
+            quality,
+            immunization,
+            telemedicine,
+            identity_verification,
+            scheduler,
+            maintenance,
+            soft_delete,
+            #[cfg(feature = "grpc")]
+            grpc,
+            events,
+            authorization,
         }
     }
 
+    /// Profile pack of the deployment's country, selected by `HimsConfig`
+    /// from `HIMS_*` variables. Patients and medical records are validated
+    /// against it as they are written.
+    fn profiles() -> Option<PackValidator> {
+        let profiles = ConfigLoader::new()
+            .with_env()
+            .load()
+            .and_then(|config| PackValidator::for_config(&config));
+        match profiles {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                tracing::error!("No profile pack loaded; resources are not validated against one: {}", e);
+                None
+            }
+        }
+    }
+
+    /// FHIR resources served, as described by `GET /metadata`
+    pub fn fhir_resources() -> Vec<FhirResource> {
+        vec![
+            FhirResource {
+                resource_type: "Patient",
+                path: "/api/v1/patients",
+                search_params: patient::PatientSearch::SEARCH_PARAMS,
+                search_include: &[],
+                search_rev_include: patient::PatientSearch::REVINCLUDES,
+                conditional_create: true,
+            },
+            FhirResource {
+                resource_type: "Appointment",
+                path: "/api/v1/appointments",
+                search_params: appointment::AppointmentSearch::SEARCH_PARAMS,
+                search_include: appointment::AppointmentSearch::INCLUDES,
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+            FhirResource {
+                resource_type: "DocumentReference",
+                path: "/api/v1/medical-records",
+                search_params: medical_record::MedicalRecordSearch::SEARCH_PARAMS,
+                search_include: &[],
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+            FhirResource {
+                resource_type: "Condition",
+                path: "/api/v1/conditions",
+                search_params: condition::ConditionSearch::SEARCH_PARAMS,
+                search_include: &[],
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+            FhirResource {
+                resource_type: "Observation",
+                path: "/api/v1/vital-signs",
+                search_params: vital_sign::VitalSignSearch::SEARCH_PARAMS,
+                search_include: &[],
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+            FhirResource {
+                resource_type: "CarePlan",
+                path: "/api/v1/care-plans",
+                search_params: care_plan::CarePlanSearch::SEARCH_PARAMS,
+                search_include: &[],
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+            FhirResource {
+                resource_type: "Task",
+                path: "/api/v1/tasks",
+                search_params: task::TaskSearch::SEARCH_PARAMS,
+                search_include: &[],
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+            FhirResource {
+                resource_type: "ServiceRequest",
+                path: "/api/v1/referrals",
+                search_params: referral::ReferralSearch::SEARCH_PARAMS,
+                search_include: &[],
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+            FhirResource {
+                resource_type: "Subscription",
+                path: "/api/v1/subscriptions",
+                search_params: &[],
+                search_include: &[],
+                search_rev_include: &[],
+                conditional_create: false,
+            },
+        ]
+    }
+
     /// Register all module routes
     pub fn routes(&self) -> Router {
-        Router::new()
-            .nest("/api/v1/patients", self.patient.routes())
-            .nest("/api/v1/appointments", self.appointment.routes())
-            .nest("/api/v1/medical-records", self.medical_record.routes())
+        let (api, mut mounted) = ApiRouter::new()
+            .nest(
+                "/api/v1/patients",
+                self.patient
+                    .routes()
+                    .merge(self.history.routes("Patient"))
+                    .merge(self.soft_delete.routes("Patient"))
+                    .merge(self.security_label.routes("Patient"))
+                    .merge(self.part2.routes())
+                    .merge(self.condition.problem_list_routes())
+                    .merge(self.vital_sign.trend_routes()),
+            )
+            .nest(
+                "/api/v1/appointments",
+                self.appointment
+                    .routes()
+                    .merge(self.history.routes("Appointment"))
+                    .merge(self.soft_delete.routes("Appointment")),
+            )
+            .nest(
+                "/api/v1/medical-records",
+                self.medical_record
+                    .routes()
+                    .merge(self.history.routes("DocumentReference"))
+                    .merge(self.soft_delete.routes("DocumentReference"))
+                    .merge(self.security_label.routes("DocumentReference"))
+                    .merge(self.attachment.routes()),
+            )
+            .nest("/api/v1/note-templates", self.note_template.routes())
+            .nest("/api/v1/provenance", self.provenance.routes())
+            .nest(
+                "/api/v1/conditions",
+                self.condition
+                    .routes()
+                    .merge(self.history.routes("Condition"))
+                    .merge(self.soft_delete.routes("Condition")),
+            )
+            .nest(
+                "/api/v1/vital-signs",
+                self.vital_sign
+                    .routes()
+                    .merge(self.history.routes("Observation"))
+                    .merge(self.soft_delete.routes("Observation")),
+            )
+            .nest("/api/v1/devices", self.device_gateway.routes())
+            .nest(
+                "/api/v1/care-plans",
+                self.care_plan
+                    .routes()
+                    .merge(self.history.routes("CarePlan"))
+                    .merge(self.soft_delete.routes("CarePlan")),
+            )
+            .nest(
+                "/api/v1/tasks",
+                self.task
+                    .routes()
+                    .merge(self.history.routes("Task"))
+                    .merge(self.soft_delete.routes("Task")),
+            )
+            .nest(
+                "/api/v1/referrals",
+                self.referral
+                    .routes()
+                    .merge(self.history.routes("ServiceRequest"))
+                    .merge(self.soft_delete.routes("ServiceRequest")),
+            )
+            .nest("/api/v1/inventory", self.inventory.routes())
+            .nest("/api/v1/theatres", self.theatre.routes())
+            .nest("/api/v1/emergency", self.emergency.routes())
+            .nest("/api/v1/radiology", self.radiology.routes())
+            .nest("/api/v1/vital-records", self.vital_record.routes())
+            .nest("/api/v1/surveillance", self.surveillance.routes())
+            .nest("/api/v1/billing", self.billing.routes())
+            .nest("/api/v1/reports", self.report.routes())
+            .nest("/api/v1/analytics", self.analytics.routes())
+            .nest("/api/v1/omop", self.omop.routes())
+            .nest("/api/v1/datasets", self.dataset.routes())
+            .nest("/api/v1/cohorts", self.cohort.routes())
+            .nest("/api/v1/quality-measures", self.quality.routes())
+            .nest("/api/v1/immunizations", self.immunization.routes())
+            .nest("/api/v1/telemedicine", self.telemedicine.routes())
+            .nest("/api/v1/identity-verification", self.identity_verification.routes())
+            .nest("/api/v1/scheduler", self.scheduler.routes())
+            .nest("/api/v1/maintenance", self.maintenance.routes())
             .nest("/api/v1/audit", self.audit.routes())
             .nest("/api/v1/auth", self.auth.routes())
+            .nest("/api/v1/roles", self.role.routes())
+            .nest("/api/v1/delegations", self.delegation.routes())
+            .nest("/api/v1/service-accounts", self.service_account.routes())
+            .nest("/api/v1/tenants", self.tenant.routes())
+            .nest("/api/v1/subscriptions", self.subscription.routes())
+            .nest("/api/v1/interface-engine", self.interface_engine.routes())
+            .nest("/api/v1/hl7", self.adt_feed.routes())
+            .nest("/api/v1/channels", self.integration.routes())
+            .nest("/api/v1/webhooks", self.webhook.routes())
+            .nest("/api/v1/events", EventsController::new(self.events.clone()).routes())
+            .nest("/api/v1/authorization", AuthorizationController::new(self.authorization.clone()).routes())
+            .nest("/api/v1/graphql", self.graphql.routes())
+            .into_parts();
+
+        let probes = self.metrics.routes();
+        mounted.extend_from_slice(probes.routes());
+        // Described from the routes actually mounted, so it cannot drift from them
+        let metadata = MetadataModule::new(&mounted, &Self::fhir_resources());
+        // gRPC shares the REST port and every layer below but is not described by metadata
+        #[cfg(feature = "grpc")]
+        let api = api.merge(self.grpc.routes());
+
+        api
+            // Resource versions written by the request record its user and reason
+            .layer(axum::middleware::from_fn(HistoryMiddleware::change_context))
+            // Responses limited to the minimum necessary for the request's principal
+            .layer(axum::middleware::from_fn_with_state(
+                self.field_filter.get_service(),
+                FieldFilterMiddleware::filter,
+            ))
+            // The request's principal, for handlers' audit logs and the history layer
+            .layer(axum::middleware::from_fn_with_state(
+                self.rate_limit.get_service(),
+                auth::AuthContext::attach,
+            ))
+            // Inside tenant resolution so keys are stored per tenant
+            .layer(axum::middleware::from_fn_with_state(
+                self.idempotency.get_service(),
+                IdempotencyMiddleware::guard,
+            ))
+            // Requests without a verified principal end here with 401, before they claim a key
+            .layer(axum::middleware::from_fn_with_state(
+                self.auth.get_middleware().service(),
+                auth::AuthMiddleware::authenticate,
+            ))
+            // API keys are stored per tenant, so they are looked up once it is resolved
+            .layer(axum::middleware::from_fn_with_state(
+                self.service_account.get_service(),
+                service_account::ApiKeyMiddleware::authenticate,
+            ))
+            // Every request runs as its resolved tenant
+            .layer(axum::middleware::from_fn_with_state(
+                self.tenant.get_service(),
+                TenantMiddleware::resolve,
+            ))
+            // Before tenant resolution so rejected requests cost no database work
+            .layer(axum::middleware::from_fn_with_state(
+                self.rate_limit.get_service(),
+                RateLimitMiddleware::limit,
+            ))
+            // Counted per matched route, including requests the tenant layer rejects
+            .layer(axum::middleware::from_fn_with_state(
+                self.metrics.get_service(),
+                MetricsMiddleware::track,
+            ))
+            // Outermost, so every log line of the request carries its correlation ID
+            .layer(axum::middleware::from_fn(crate::core::logger::correlation_id))
+            // Probes and metadata sit outside tenant resolution so they answer without a tenant
+            .merge(probes.into_router())
+            .merge(metadata.routes().into_router())
     }
-}
\ No newline at end of file
+}
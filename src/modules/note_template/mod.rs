@@ -0,0 +1,51 @@
+//! Note Template Module
+//!
+//! This module provides clinical note templates including:
+//! - Built-in SOAP progress note and discharge summary templates
+//! - Tenant-defined templates with typed fields in sections
+//! - Fields bound to FHIR elements, prefilled from patient context
+//! - Structured data rendered into a record's content
+//! - Required fields checked before a record is finalized
+
+#[path = "note_template.controller.rs"]
+pub mod note_template_controller;
+#[path = "note_template.service.rs"]
+pub mod note_template_service;
+#[path = "note_template.sql.rs"]
+pub mod note_template_sql;
+
+pub use note_template_controller::NoteTemplateController;
+pub use note_template_service::{
+    FieldType, NoteTemplate, NoteTemplateService, PrefilledNote, TemplateContext, TemplateField, TemplateValidation,
+};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Note Template Module Configuration
+pub struct NoteTemplateModule {
+    pub service: Arc<NoteTemplateService>,
+    pub controller: Arc<NoteTemplateController>,
+}
+
+impl NoteTemplateModule {
+    /// Create a new Note Template Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(NoteTemplateService::new(db_pool));
+        let controller = Arc::new(NoteTemplateController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<NoteTemplateService> {
+        self.service.clone()
+    }
+}
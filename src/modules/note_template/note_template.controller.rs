@@ -0,0 +1,174 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::note_template::note_template_service::{NoteTemplate, PrefilledNote, TemplateValidation};
+use crate::modules::note_template::NoteTemplateService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Patient and encounter to prefill a template for
+#[derive(Debug, Default, Deserialize)]
+pub struct PrefillRequest {
+    pub patient_id: Option<Uuid>,
+    pub encounter_id: Option<Uuid>,
+}
+
+/// Structured data to check against a template
+#[derive(Debug, Deserialize)]
+pub struct ValidateRequest {
+    pub structured_data: Value,
+    /// Also require the fields a final record needs
+    #[serde(default)]
+    pub complete: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Note template controller for template definitions and structured data
+/// capture
+pub struct NoteTemplateController {
+    template_service: Arc<NoteTemplateService>,
+}
+
+impl NoteTemplateController {
+    /// Create new controller with injected service
+    pub fn new(template_service: Arc<NoteTemplateService>) -> Self {
+        Self { template_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/", Self::list_templates, "List note templates")
+            .post("/", Self::create_template, "Define a note template")
+            .get("/:id", Self::get_template, "Get note template by ID")
+            .put("/:id", Self::update_template, "Update a note template")
+            .post("/:id/prefill", Self::prefill, "Prefill a note template from patient context")
+            .post("/:id/validate", Self::validate, "Check structured data against a note template")
+            .with_state(self.template_service.clone())
+    }
+
+    /// Built-in templates, then the tenant's
+    pub async fn list_templates(
+        State(service): State<Arc<NoteTemplateService>>,
+    ) -> Result<Json<Vec<NoteTemplate>>, ErrorReply> {
+        service
+            .list()
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list note templates", e))
+    }
+
+    /// Get note template by ID
+    pub async fn get_template(
+        State(service): State<Arc<NoteTemplateService>>,
+        Path(id): Path<String>,
+    ) -> Result<Json<NoteTemplate>, ErrorReply> {
+        match service.get(&id).await {
+            Ok(Some(template)) => Ok(Json(template)),
+            Ok(None) => Err(Self::not_found(&id)),
+            Err(e) => Err(Self::error_response("Failed to get note template", e)),
+        }
+    }
+
+    /// Define a tenant template
+    pub async fn create_template(
+        State(service): State<Arc<NoteTemplateService>>,
+        headers: HeaderMap,
+        Json(template): Json<NoteTemplate>,
+    ) -> Result<(StatusCode, Json<NoteTemplate>), ErrorReply> {
+        tracing::info!("Defining note template {}", template.id);
+
+        let created_by = extract_user_from_headers(&headers).ok();
+        match service.create(template, created_by).await {
+            Ok(template) => Ok((StatusCode::CREATED, Json(template))),
+            Err(e) => Err(Self::error_response("Failed to define note template", e)),
+        }
+    }
+
+    /// Replace a tenant template; the ID in the path wins over the body's
+    pub async fn update_template(
+        State(service): State<Arc<NoteTemplateService>>,
+        Path(id): Path<String>,
+        Json(mut template): Json<NoteTemplate>,
+    ) -> Result<Json<NoteTemplate>, ErrorReply> {
+        tracing::info!("Updating note template {}", id);
+
+        template.id = id.clone();
+        match service.update(template).await {
+            Ok(Some(template)) => Ok(Json(template)),
+            Ok(None) => Err(Self::not_found(&id)),
+            Err(e) => Err(Self::error_response("Failed to update note template", e)),
+        }
+    }
+
+    /// The template's fields filled in from the patient and encounter, and
+    /// the note they render to
+    pub async fn prefill(
+        State(service): State<Arc<NoteTemplateService>>,
+        Path(id): Path<String>,
+        Json(request): Json<PrefillRequest>,
+    ) -> Result<Json<PrefilledNote>, ErrorReply> {
+        match service.prefill(&id, request.patient_id, request.encounter_id).await {
+            Ok(Some(note)) => Ok(Json(note)),
+            Ok(None) => Err(Self::not_found(&id)),
+            Err(e) => Err(Self::error_response("Failed to prefill note template", e)),
+        }
+    }
+
+    /// Issues with structured data, so forms can show them before a record
+    /// is saved or finalized
+    pub async fn validate(
+        State(service): State<Arc<NoteTemplateService>>,
+        Path(id): Path<String>,
+        Json(request): Json<ValidateRequest>,
+    ) -> Result<Json<TemplateValidation>, ErrorReply> {
+        match service.get(&id).await {
+            Ok(Some(template)) => Ok(Json(template.validate(&request.structured_data, request.complete))),
+            Ok(None) => Err(Self::not_found(&id)),
+            Err(e) => Err(Self::error_response("Failed to validate structured data", e)),
+        }
+    }
+
+    fn not_found(id: &str) -> ErrorReply {
+        tracing::warn!("Note template not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Note template not found".to_string(),
+                message: format!("Note template {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,587 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{MedicalRecordType, Patient};
+use crate::modules::patient::PatientService;
+
+// Import SQL queries from separate file
+use crate::modules::note_template::note_template_sql::*;
+
+/// Type of a template field's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldType {
+    Text,
+    Number,
+    /// `YYYY-MM-DD`
+    Date,
+    Boolean,
+    /// A code as a string, or a Coding with `system`, `code` and `display`
+    Code,
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Number => "number",
+            FieldType::Date => "date",
+            FieldType::Boolean => "boolean",
+            FieldType::Code => "code",
+        }
+    }
+}
+
+/// A structured field of a note template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateField {
+    /// Key of the value in a record's structured data
+    pub name: String,
+    pub label: String,
+    pub field_type: FieldType,
+    /// Heading the field renders under
+    pub section: String,
+    /// Must have a value before the record is finalized
+    #[serde(default)]
+    pub required: bool,
+    /// FHIR element the value maps to, e.g. `Condition.code`
+    #[serde(default)]
+    pub binding: Option<String>,
+}
+
+impl TemplateField {
+    fn new(name: &str, label: &str, field_type: FieldType, section: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            label: label.to_string(),
+            field_type,
+            section: section.to_string(),
+            required: false,
+            binding: None,
+        }
+    }
+
+    fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    fn bound_to(mut self, element: &str) -> Self {
+        self.binding = Some(element.to_string());
+        self
+    }
+
+    /// Whether `value` is of this field's type
+    fn accepts(&self, value: &Value) -> bool {
+        match (self.field_type, value) {
+            (FieldType::Text, Value::String(_)) => true,
+            (FieldType::Number, Value::Number(_)) => true,
+            (FieldType::Date, Value::String(date)) => NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok(),
+            (FieldType::Boolean, Value::Bool(_)) => true,
+            (FieldType::Code, Value::String(code)) => !code.trim().is_empty(),
+            (FieldType::Code, Value::Object(coding)) => coding.get("code").and_then(Value::as_str).is_some(),
+            _ => false,
+        }
+    }
+
+    /// The value as it reads in a rendered note
+    fn display(&self, value: &Value) -> String {
+        match value {
+            Value::String(text) => text.clone(),
+            Value::Bool(true) => "Yes".to_string(),
+            Value::Bool(false) => "No".to_string(),
+            Value::Object(coding) => coding
+                .get("display")
+                .or_else(|| coding.get("code"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// A field whose value is missing or invalid
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldIssue {
+    pub field: String,
+    pub message: String,
+}
+
+/// Result of checking structured data against a template
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateValidation {
+    pub valid: bool,
+    pub issues: Vec<FieldIssue>,
+}
+
+/// A note template: structured fields in sections, rendered into a
+/// record's content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteTemplate {
+    /// Lowercase slug, e.g. `soap`
+    pub id: String,
+    pub title: String,
+    pub record_type: MedicalRecordType,
+    pub fields: Vec<TemplateField>,
+    /// Shipped with the server rather than defined by the tenant
+    #[serde(default)]
+    pub built_in: bool,
+}
+
+/// Patient and encounter a note is being written for
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub patient: Option<Patient>,
+    pub encounter_id: Option<Uuid>,
+}
+
+/// A template filled in from context, ready for the clinician to complete
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefilledNote {
+    pub template_id: String,
+    pub structured_data: Value,
+    /// The structured data rendered as a note
+    pub content: String,
+}
+
+impl NoteTemplate {
+    /// SOAP progress note and discharge summary templates every tenant has
+    pub fn built_in() -> Vec<NoteTemplate> {
+        use FieldType::*;
+
+        let soap = NoteTemplate {
+            id: "soap".to_string(),
+            title: "SOAP Progress Note".to_string(),
+            record_type: MedicalRecordType::ProgressNote,
+            fields: vec![
+                TemplateField::new("patient_name", "Patient", Text, "Patient").bound_to("Patient.name"),
+                TemplateField::new("birth_date", "Date of birth", Date, "Patient").bound_to("Patient.birthDate"),
+                TemplateField::new("chief_complaint", "Chief complaint", Text, "Subjective")
+                    .required()
+                    .bound_to("Encounter.reasonCode"),
+                TemplateField::new("subjective", "History", Text, "Subjective").required(),
+                TemplateField::new("objective", "Examination", Text, "Objective").required(),
+                TemplateField::new("assessment", "Assessment", Text, "Assessment").required(),
+                TemplateField::new("diagnosis", "Diagnosis", Code, "Assessment").bound_to("Condition.code"),
+                TemplateField::new("plan", "Plan", Text, "Plan").required(),
+                TemplateField::new("follow_up_date", "Follow-up", Date, "Plan").bound_to("Appointment.start"),
+            ],
+            built_in: true,
+        };
+
+        let discharge_summary = NoteTemplate {
+            id: "discharge-summary".to_string(),
+            title: "Discharge Summary".to_string(),
+            record_type: MedicalRecordType::DischargeSummary,
+            fields: vec![
+                TemplateField::new("patient_name", "Patient", Text, "Patient").bound_to("Patient.name"),
+                TemplateField::new("patient_identifier", "MRN", Text, "Patient").bound_to("Patient.identifier"),
+                TemplateField::new("admission_date", "Admitted", Date, "Admission")
+                    .required()
+                    .bound_to("Encounter.period.start"),
+                TemplateField::new("discharge_date", "Discharged", Date, "Admission")
+                    .required()
+                    .bound_to("Encounter.period.end"),
+                TemplateField::new("admitting_diagnosis", "Admitting diagnosis", Code, "Diagnoses")
+                    .required()
+                    .bound_to("Condition.code"),
+                TemplateField::new("discharge_diagnosis", "Discharge diagnosis", Code, "Diagnoses")
+                    .required()
+                    .bound_to("Condition.code"),
+                TemplateField::new("hospital_course", "Hospital course", Text, "Hospital Course").required(),
+                TemplateField::new("discharge_medications", "Medications", Text, "Discharge Plan")
+                    .bound_to("MedicationRequest.medicationCodeableConcept"),
+                TemplateField::new("discharge_disposition", "Disposition", Code, "Discharge Plan")
+                    .bound_to("Encounter.hospitalization.dischargeDisposition"),
+                TemplateField::new("follow_up", "Follow-up", Text, "Discharge Plan").required(),
+            ],
+            built_in: true,
+        };
+
+        vec![soap, discharge_summary]
+    }
+
+    /// Reject a template without an ID, with no fields, or with duplicate,
+    /// unnamed or unbindable fields
+    pub fn check_definition(&self) -> Result<(), HimsError> {
+        let invalid = |message: String| HimsError::ValidationError { message };
+        let slug = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
+        if self.id.is_empty() || self.id.len() > 64 || self.id.starts_with('-') || !self.id.chars().all(slug) {
+            return Err(invalid(format!("Template ID {:?} must be a lowercase slug", self.id)));
+        }
+        if self.title.trim().is_empty() {
+            return Err(invalid("A template needs a title".to_string()));
+        }
+        if self.fields.is_empty() {
+            return Err(invalid("A template needs at least one field".to_string()));
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            if field.name.trim().is_empty() || field.label.trim().is_empty() || field.section.trim().is_empty() {
+                return Err(invalid(format!("Field {} needs a name, label and section", i + 1)));
+            }
+            if self.fields[..i].iter().any(|other| other.name == field.name) {
+                return Err(invalid(format!("Field {} appears more than once", field.name)));
+            }
+            if let Some(binding) = &field.binding {
+                let mut path = binding.split('.');
+                let resource = path.next().unwrap_or_default();
+                let bindable = resource.starts_with(|c: char| c.is_ascii_uppercase())
+                    && path.clone().count() > 0
+                    && path.all(|element| !element.is_empty() && element.chars().all(|c| c.is_ascii_alphanumeric()));
+                if !bindable {
+                    return Err(invalid(format!("Field {} binds to {:?}, not a FHIR element path", field.name, binding)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Problems with `data`: unknown fields and values of the wrong type,
+    /// and, when `complete`, required fields without a value
+    pub fn validate(&self, data: &Value, complete: bool) -> TemplateValidation {
+        let mut issues = Vec::new();
+        let empty = Map::new();
+        let values = match data {
+            Value::Object(values) => values,
+            Value::Null => &empty,
+            _ => {
+                issues.push(FieldIssue {
+                    field: String::new(),
+                    message: "Structured data must be an object of field values".to_string(),
+                });
+                &empty
+            }
+        };
+
+        for name in values.keys() {
+            if !self.fields.iter().any(|field| &field.name == name) {
+                issues.push(FieldIssue {
+                    field: name.clone(),
+                    message: format!("{} is not a field of template {}", name, self.id),
+                });
+            }
+        }
+        for field in &self.fields {
+            match values.get(&field.name).filter(|value| !is_blank(value)) {
+                Some(value) if !field.accepts(value) => issues.push(FieldIssue {
+                    field: field.name.clone(),
+                    message: format!("{} must be a {} value", field.label, field.field_type.as_str()),
+                }),
+                None if complete && field.required => issues.push(FieldIssue {
+                    field: field.name.clone(),
+                    message: format!("{} is required", field.label),
+                }),
+                _ => {}
+            }
+        }
+
+        TemplateValidation {
+            valid: issues.is_empty(),
+            issues,
+        }
+    }
+
+    /// Fail with `ValidationError` listing every issue [`Self::validate`]
+    /// finds
+    pub fn check(&self, data: &Value, complete: bool) -> Result<(), HimsError> {
+        let validation = self.validate(data, complete);
+        if validation.valid {
+            return Ok(());
+        }
+        let issues: Vec<String> = validation.issues.into_iter().map(|issue| issue.message).collect();
+        Err(HimsError::ValidationError {
+            message: format!("Note does not match template {}: {}", self.id, issues.join("; ")),
+        })
+    }
+
+    /// Values for the fields bound to elements the context has
+    pub fn prefill(&self, context: &TemplateContext) -> Value {
+        let mut values = Map::new();
+        for field in &self.fields {
+            let value = field.binding.as_deref().and_then(|binding| Self::resolve(binding, context));
+            if let Some(value) = value {
+                values.insert(field.name.clone(), value);
+            }
+        }
+        Value::Object(values)
+    }
+
+    /// Value of a bound element from the context, for the elements a
+    /// context can supply
+    fn resolve(binding: &str, context: &TemplateContext) -> Option<Value> {
+        let patient = context.patient.as_ref();
+        match binding {
+            "Patient.name" => {
+                let name = patient?.name.first()?;
+                let text = name.text.clone().unwrap_or_else(|| {
+                    name.given.iter().chain(name.family.iter()).cloned().collect::<Vec<_>>().join(" ")
+                });
+                (!text.trim().is_empty()).then_some(Value::String(text))
+            }
+            "Patient.birthDate" => patient?.birth_date.map(|date| Value::String(date.format("%Y-%m-%d").to_string())),
+            "Patient.gender" => serde_json::to_value(&patient?.gender).ok(),
+            "Patient.identifier" => patient?.identifier.first().map(|identifier| Value::String(identifier.value.clone())),
+            "Encounter.id" => context.encounter_id.map(|id| Value::String(id.to_string())),
+            _ => None,
+        }
+    }
+
+    /// The note as text: each section's heading, then a line per field
+    /// with a value, in template order
+    pub fn render(&self, data: &Value) -> String {
+        let mut sections: Vec<(&str, Vec<String>)> = Vec::new();
+        for field in &self.fields {
+            let Some(value) = data.get(&field.name).filter(|value| !is_blank(value)) else {
+                continue;
+            };
+            let line = format!("{}: {}", field.label, field.display(value));
+            match sections.iter_mut().find(|(section, _)| *section == field.section) {
+                Some((_, lines)) => lines.push(line),
+                None => sections.push((field.section.as_str(), vec![line])),
+            }
+        }
+
+        let mut note = format!("{}\n", self.title);
+        for (section, lines) in sections {
+            note.push_str(&format!("\n{}\n{}\n", section.to_uppercase(), lines.join("\n")));
+        }
+        note
+    }
+}
+
+/// A value that counts as not filled in
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Note template service: built-in templates plus each tenant's own
+#[derive(Debug, Clone)]
+pub struct NoteTemplateService {
+    pool: PgPool,
+    patients: PatientService,
+}
+
+impl NoteTemplateService {
+    /// Create new note template service
+    pub fn new(pool: PgPool) -> Self {
+        let patients = PatientService::new(pool.clone());
+        Self { pool, patients }
+    }
+
+    /// Built-in templates, then the tenant's
+    pub async fn list(&self) -> Result<Vec<NoteTemplate>, HimsError> {
+        let rows = sqlx::query(LIST_NOTE_TEMPLATES)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut templates = NoteTemplate::built_in();
+        for row in rows {
+            templates.push(Self::template_from_row(&row)?);
+        }
+        Ok(templates)
+    }
+
+    /// A built-in or tenant template
+    pub async fn get(&self, id: &str) -> Result<Option<NoteTemplate>, HimsError> {
+        if let Some(template) = NoteTemplate::built_in().into_iter().find(|template| template.id == id) {
+            return Ok(Some(template));
+        }
+        let row = sqlx::query(GET_NOTE_TEMPLATE)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        row.as_ref().map(Self::template_from_row).transpose()
+    }
+
+    /// A template that must exist, as records name it
+    pub async fn require(&self, id: &str) -> Result<NoteTemplate, HimsError> {
+        self.get(id).await?.ok_or_else(|| HimsError::ValidationError {
+            message: format!("Unknown note template: {}", id),
+        })
+    }
+
+    /// Define a tenant template; built-in IDs are taken
+    pub async fn create(&self, template: NoteTemplate, created_by: Option<Uuid>) -> Result<NoteTemplate, HimsError> {
+        template.check_definition()?;
+        if NoteTemplate::built_in().iter().any(|built_in| built_in.id == template.id) {
+            return Err(HimsError::ConflictError {
+                message: format!("{} is a built-in template", template.id),
+            });
+        }
+
+        let row = sqlx::query(INSERT_NOTE_TEMPLATE)
+            .bind(&template.id)
+            .bind(&template.title)
+            .bind(record_type_code(&template.record_type))
+            .bind(serde_json::to_value(&template.fields).map_err(|e| HimsError::InternalError { message: e.to_string() })?)
+            .bind(created_by)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError {
+                    message: format!("Template {} already exists", template.id),
+                },
+                e => HimsError::DatabaseError(e.to_string()),
+            })?;
+        Self::template_from_row(&row)
+    }
+
+    /// Replace a tenant template. Returns `None` if the tenant has no such
+    /// template; built-in templates cannot be changed.
+    pub async fn update(&self, template: NoteTemplate) -> Result<Option<NoteTemplate>, HimsError> {
+        template.check_definition()?;
+        if NoteTemplate::built_in().iter().any(|built_in| built_in.id == template.id) {
+            return Err(HimsError::ConflictError {
+                message: format!("{} is a built-in template and cannot be changed", template.id),
+            });
+        }
+
+        let row = sqlx::query(UPDATE_NOTE_TEMPLATE)
+            .bind(&template.id)
+            .bind(&template.title)
+            .bind(record_type_code(&template.record_type))
+            .bind(serde_json::to_value(&template.fields).map_err(|e| HimsError::InternalError { message: e.to_string() })?)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        row.as_ref().map(Self::template_from_row).transpose()
+    }
+
+    /// Fill in a template from the patient and encounter. Returns `None`
+    /// if there is no such template.
+    pub async fn prefill(
+        &self,
+        id: &str,
+        patient_id: Option<Uuid>,
+        encounter_id: Option<Uuid>,
+    ) -> Result<Option<PrefilledNote>, HimsError> {
+        let Some(template) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let patient = match patient_id {
+            Some(patient_id) => Some(
+                self.patients
+                    .get_patient(patient_id)
+                    .await
+                    .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+                    .ok_or_else(|| HimsError::ValidationError {
+                        message: format!("Patient not found: {}", patient_id),
+                    })?,
+            ),
+            None => None,
+        };
+
+        let structured_data = template.prefill(&TemplateContext { patient, encounter_id });
+        let content = template.render(&structured_data);
+        Ok(Some(PrefilledNote {
+            template_id: template.id,
+            structured_data,
+            content,
+        }))
+    }
+
+    fn template_from_row(row: &PgRow) -> Result<NoteTemplate, HimsError> {
+        let fields = serde_json::from_value(row.get("fields")).map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(NoteTemplate {
+            id: row.get("id"),
+            title: row.get("title"),
+            record_type: MedicalRecordType::from_string(&row.get::<String, _>("record_type")),
+            fields,
+            built_in: false,
+        })
+    }
+}
+
+/// Record type code, as the note_templates table stores it
+fn record_type_code(record_type: &MedicalRecordType) -> String {
+    match serde_json::to_value(record_type) {
+        Ok(Value::String(code)) => code,
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn soap() -> NoteTemplate {
+        NoteTemplate::built_in().into_iter().find(|template| template.id == "soap").unwrap()
+    }
+
+    #[test]
+    fn test_built_in_templates_are_well_formed() {
+        for template in NoteTemplate::built_in() {
+            template.check_definition().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_check_definition() {
+        let mut template = soap();
+        template.id = "Custom Note".to_string();
+        assert!(template.check_definition().is_err());
+
+        let mut template = soap();
+        template.fields.push(template.fields[0].clone());
+        assert!(template.check_definition().is_err());
+
+        let mut template = soap();
+        template.fields[0].binding = Some("patient name".to_string());
+        assert!(template.check_definition().is_err());
+    }
+
+    #[test]
+    fn test_validate_types_and_required_fields() {
+        let template = soap();
+        let draft = json!({ "chief_complaint": "Cough", "birth_date": "1980-02-30", "vitals": "BP 120/80" });
+        let validation = template.validate(&draft, false);
+        let fields: Vec<&str> = validation.issues.iter().map(|issue| issue.field.as_str()).collect();
+        assert_eq!(fields, vec!["vitals", "birth_date"]);
+
+        let complete = json!({
+            "chief_complaint": "Cough",
+            "subjective": "Dry cough for 3 days",
+            "objective": "Chest clear",
+            "assessment": "Viral URTI",
+            "diagnosis": { "system": "http://snomed.info/sct", "code": "54150009", "display": "URTI" },
+            "plan": "Fluids, review if worse",
+        });
+        assert!(template.validate(&complete, true).valid);
+        let mut incomplete = complete.clone();
+        incomplete["plan"] = json!("  ");
+        assert!(template.check(&incomplete, false).is_ok());
+        assert!(template.check(&incomplete, true).is_err());
+    }
+
+    #[test]
+    fn test_prefill_and_render() {
+        let template = soap();
+        let context = TemplateContext::default();
+        assert_eq!(template.prefill(&context), json!({}));
+
+        let data = json!({
+            "patient_name": "Asha Rao",
+            "chief_complaint": "Cough",
+            "plan": "Review in a week",
+            "diagnosis": { "code": "54150009", "display": "URTI" },
+        });
+        let note = template.render(&data);
+        assert!(note.starts_with("SOAP Progress Note\n"));
+        assert!(note.contains("\nPATIENT\nPatient: Asha Rao\n"));
+        assert!(note.contains("\nASSESSMENT\nDiagnosis: URTI\n"));
+        assert!(note.find("SUBJECTIVE").unwrap() < note.find("PLAN").unwrap());
+    }
+}
@@ -0,0 +1,34 @@
+//! Note Template SQL Queries
+//!
+//! This file contains all SQL queries used by the note template service
+//! for clean separation of concerns and better maintainability.
+
+/// Insert a tenant's note template
+pub const INSERT_NOTE_TEMPLATE: &str = r#"
+    INSERT INTO note_templates (id, title, record_type, fields, created_by)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING id, title, record_type, fields
+"#;
+
+/// Get one of the tenant's note templates
+pub const GET_NOTE_TEMPLATE: &str = r#"
+    SELECT id, title, record_type, fields
+    FROM note_templates
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// List the tenant's note templates by ID
+pub const LIST_NOTE_TEMPLATES: &str = r#"
+    SELECT id, title, record_type, fields
+    FROM note_templates
+    WHERE deleted_at IS NULL
+    ORDER BY id
+"#;
+
+/// Replace a tenant's note template; records keep the values they have
+pub const UPDATE_NOTE_TEMPLATE: &str = r#"
+    UPDATE note_templates
+    SET title = $2, record_type = $3, fields = $4, updated_at = NOW()
+    WHERE id = $1 AND deleted_at IS NULL
+    RETURNING id, title, record_type, fields
+"#;
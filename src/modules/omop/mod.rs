@@ -0,0 +1,51 @@
+//! OMOP Module
+//!
+//! This module exports records for research data warehouses including:
+//! - Patients, visits, conditions, drug exposures and measurements as OMOP CDM tables
+//! - Source codes mapped to standard concepts through the loaded OMOP vocabularies
+//! - Pseudonymous, stable record IDs, so a warehouse can reload an export
+//! - 42 CFR Part 2 records left out without the patient's consent to research
+
+#[path = "omop.controller.rs"]
+pub mod omop_controller;
+#[path = "omop.service.rs"]
+pub mod omop_service;
+#[path = "omop.sql.rs"]
+pub mod omop_sql;
+
+pub use omop_controller::OmopController;
+pub use omop_service::{ConceptMapping, OmopExport, OmopExportQuery, OmopService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// OMOP Module Configuration
+pub struct OmopModule {
+    pub service: Arc<OmopService>,
+    pub controller: Arc<OmopController>,
+}
+
+impl OmopModule {
+    /// Create a new OMOP Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(OmopService::new(db_pool));
+        let controller = Arc::new(OmopController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<OmopService> {
+        self.service.clone()
+    }
+}
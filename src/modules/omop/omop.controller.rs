@@ -0,0 +1,115 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::omop::omop_service::{LoadConceptsRequest, LoadConceptsResponse, OmopExportQuery};
+use crate::modules::omop::OmopService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// OMOP controller for research exports in the OMOP Common Data Model
+pub struct OmopController {
+    omop_service: Arc<OmopService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl OmopController {
+    /// Create new controller with injected service
+    pub fn new(omop_service: Arc<OmopService>) -> Self {
+        Self { omop_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/export", Self::export, "Export records as OMOP CDM tables")
+            .post("/concepts", Self::load_concepts, "Load concepts of the OMOP vocabularies")
+            .with_state(self.omop_service.clone())
+    }
+
+    /// Export records as OMOP CDM tables, a ZIP archive of CSV files
+    pub async fn export(
+        State(service): State<Arc<OmopService>>,
+        headers: HeaderMap,
+        Query(query): Query<OmopExportQuery>,
+    ) -> Result<Response, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.export(&query, actor).await {
+            Ok(export) => {
+                let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", export.file_name))
+                    .unwrap_or(HeaderValue::from_static("attachment"));
+                Ok((
+                    [
+                        (header::CONTENT_TYPE, HeaderValue::from_static("application/zip")),
+                        (header::CONTENT_DISPOSITION, disposition),
+                        (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                        (header::CACHE_CONTROL, HeaderValue::from_static("private, no-store")),
+                    ],
+                    export.bytes,
+                )
+                    .into_response())
+            }
+            Err(e) => Err(Self::error_response("Failed to export OMOP CDM tables", e)),
+        }
+    }
+
+    /// Load concepts of the OMOP vocabularies
+    pub async fn load_concepts(
+        State(service): State<Arc<OmopService>>,
+        headers: HeaderMap,
+        Json(payload): Json<LoadConceptsRequest>,
+    ) -> Result<Json<LoadConceptsResponse>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.load_concepts(&payload.concepts, actor).await {
+            Ok(loaded) => Ok(Json(LoadConceptsResponse { loaded })),
+            Err(e) => Err(Self::error_response("Failed to load OMOP concepts", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,356 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::exporters::omop::{
+    omop_extract, OmopConcept, OmopConditionSource, OmopDrugSource, OmopMeasurementSource, OmopPersonSource,
+    OmopSource, OmopVisitSource, OmopVocabulary, SourceCode,
+};
+use crate::models::{CodeableConcept, VitalSignComponent};
+use crate::modules::authorization::{Action, PurposeOfUse, Subject};
+use crate::modules::part2::{is_part2_row, Part2Consents};
+use crate::modules::role::RoleService;
+
+// Import SQL queries
+use crate::modules::omop::omop_sql::*;
+
+/// Most concepts one request may load
+const MAX_CONCEPTS: usize = 10_000;
+
+/// Period an export covers, every record's when unbounded
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OmopExportQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// An export, as a ZIP archive of the CDM tables as CSV
+#[derive(Debug, Clone)]
+pub struct OmopExport {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The concept of a source code, from the OMOP vocabularies
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConceptMapping {
+    /// OMOP vocabulary, e.g. `ICD10CM`
+    pub vocabulary_id: String,
+    pub concept_code: String,
+    pub concept_id: i32,
+    /// Standard concept the code maps to, if any
+    pub standard_concept_id: Option<i32>,
+    pub concept_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoadConceptsRequest {
+    pub concepts: Vec<ConceptMapping>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadConceptsResponse {
+    pub loaded: usize,
+}
+
+fn codes(value: Option<serde_json::Value>) -> Vec<SourceCode> {
+    value
+        .and_then(|value| serde_json::from_value::<CodeableConcept>(value).ok())
+        .map(|concept| SourceCode::from_concept(&concept))
+        .unwrap_or_default()
+}
+
+fn loinc(code: &str) -> Vec<SourceCode> {
+    vec![SourceCode { vocabulary_id: "LOINC".to_string(), concept_code: code.to_string() }]
+}
+
+fn visit_from_row(row: &PgRow) -> OmopVisitSource {
+    OmopVisitSource {
+        key: row.get("key"),
+        person_key: row.get("person_key"),
+        class_code: row.get("class_code"),
+        start: row.get("start_at"),
+        end: row.get("end_at"),
+    }
+}
+
+fn condition_from_row(row: &PgRow) -> OmopConditionSource {
+    OmopConditionSource {
+        key: row.get("key"),
+        person_key: row.get("person_key"),
+        visit_key: row.get("visit_key"),
+        codes: codes(row.get("code")),
+        start: row.get("start_at"),
+        end: row.get("end_at"),
+    }
+}
+
+fn drug_from_row(row: &PgRow) -> OmopDrugSource {
+    OmopDrugSource {
+        key: row.get("key"),
+        person_key: row.get("person_key"),
+        visit_key: row.get("visit_key"),
+        codes: codes(row.get("code")),
+        start: row.get("start_at"),
+        quantity: row.get("quantity"),
+        days_supply: row.get("days_supply"),
+        refills: row.get("refills"),
+    }
+}
+
+fn observation_from_row(row: &PgRow) -> OmopMeasurementSource {
+    OmopMeasurementSource {
+        key: row.get("key"),
+        person_key: row.get("person_key"),
+        visit_key: row.get("visit_key"),
+        codes: codes(row.get("code")),
+        at: row.get("start_at"),
+        value: row.get("value"),
+        unit: row.get("unit"),
+    }
+}
+
+/// A vital sign's measurements: itself, or each of its components, keyed
+/// by the sign and the component's code
+fn vital_sign_from_row(row: &PgRow) -> Vec<OmopMeasurementSource> {
+    let key: String = row.get("key");
+    let person_key: String = row.get("person_key");
+    let visit_key: Option<String> = row.get("visit_key");
+    let at: DateTime<Utc> = row.get("start_at");
+    let value: Option<f64> = row.get("value");
+    if value.is_some() {
+        let code: String = row.get("loinc_code");
+        return vec![OmopMeasurementSource {
+            key,
+            person_key,
+            visit_key,
+            codes: loinc(&code),
+            at,
+            value,
+            unit: row.get("unit"),
+        }];
+    }
+    let components: Vec<VitalSignComponent> = serde_json::from_value(row.get("component")).unwrap_or_default();
+    components
+        .into_iter()
+        .map(|component| OmopMeasurementSource {
+            key: format!("{}/{}", key, component.kind.loinc_code()),
+            person_key: person_key.clone(),
+            visit_key: visit_key.clone(),
+            codes: loinc(component.kind.loinc_code()),
+            at,
+            value: Some(component.value.value),
+            unit: component.value.code,
+        })
+        .collect()
+}
+
+fn check_concept(concept: &ConceptMapping) -> Result<(), String> {
+    if concept.vocabulary_id.trim().is_empty() || concept.vocabulary_id.len() > 20 {
+        return Err(format!("Vocabulary '{}' is not an OMOP vocabulary ID", concept.vocabulary_id));
+    }
+    if concept.concept_code.trim().is_empty() || concept.concept_code.len() > 50 {
+        return Err(format!("Code '{}' is not an OMOP concept code", concept.concept_code));
+    }
+    if concept.concept_id <= 0 || concept.standard_concept_id.is_some_and(|id| id < 0) {
+        return Err(format!("Concept IDs of {} {} are not valid", concept.vocabulary_id, concept.concept_code));
+    }
+    Ok(())
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+/// OMOP export service: clinical records as OMOP CDM tables for research
+/// data warehouses, their codes mapped to standard concepts through the
+/// vocabularies loaded into `omop_concepts`
+pub struct OmopService {
+    pool: PgPool,
+    role_service: RoleService,
+    part2: Part2Consents,
+}
+
+impl OmopService {
+    /// Create new OMOP export service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            part2: Part2Consents::new(pool.clone()),
+            pool,
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Export the records of a period as CDM tables. Records are read from
+    /// one snapshot, so the tables agree with each other. Part 2 records
+    /// are withheld without the patient's consent for the exporting user to
+    /// use them in research.
+    pub async fn export(&self, query: &OmopExportQuery, actor: Uuid) -> Result<OmopExport, HimsError> {
+        self.require(actor, &[Action::ResearchAccess, Action::ExportData]).await?;
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if from > to {
+                return Err(validation_error(format!("from ({}) is after to ({})", from, to)));
+            }
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+
+        let rows = |sql: &'static str| sqlx::query(sql).bind(query.from).bind(query.to);
+        let visits = rows(LIST_VISITS).fetch_all(&mut *tx).await.map_err(database_error)?;
+        let conditions = rows(LIST_CONDITIONS).fetch_all(&mut *tx).await.map_err(database_error)?;
+        let drugs = rows(LIST_DRUGS).fetch_all(&mut *tx).await.map_err(database_error)?;
+        let observations = rows(LIST_OBSERVATIONS).fetch_all(&mut *tx).await.map_err(database_error)?;
+        let vital_signs = rows(LIST_VITAL_SIGNS).fetch_all(&mut *tx).await.map_err(database_error)?;
+
+        // Part 2 records, and every record of a labelled patient, stay out
+        // of the export unless the patient consented to research
+        let recorded: HashSet<Uuid> = [&visits, &conditions, &drugs, &observations, &vital_signs]
+            .into_iter()
+            .flatten()
+            .map(|row| row.get("patient_id"))
+            .collect();
+        let recorded: Vec<Uuid> = recorded.into_iter().collect();
+        let disclosures = self
+            .part2
+            .disclosures(&recorded, &Subject::User(actor), PurposeOfUse::Research)
+            .await?;
+        let mut patients: HashSet<Uuid> = HashSet::new();
+        let mut withheld = 0;
+        let mut disclosed = |row: &&PgRow| {
+            let patient_id: Uuid = row.get("patient_id");
+            let resource: serde_json::Value = row.get("resource");
+            let disclosed = disclosures
+                .get(&patient_id)
+                .is_some_and(|disclosure| disclosure.discloses(is_part2_row(&resource)));
+            if disclosed {
+                patients.insert(patient_id);
+            } else {
+                withheld += 1;
+            }
+            disclosed
+        };
+
+        let mut source = OmopSource::default();
+        for row in visits.iter().filter(&mut disclosed) {
+            source.visits.push(visit_from_row(row));
+        }
+        for row in conditions.iter().filter(&mut disclosed) {
+            source.conditions.push(condition_from_row(row));
+        }
+        for row in drugs.iter().filter(&mut disclosed) {
+            source.drugs.push(drug_from_row(row));
+        }
+        for row in observations.iter().filter(&mut disclosed) {
+            source.measurements.push(observation_from_row(row));
+        }
+        for row in vital_signs.iter().filter(&mut disclosed) {
+            source.measurements.extend(vital_sign_from_row(row));
+        }
+
+        let patients: Vec<Uuid> = patients.into_iter().collect();
+        source.persons = sqlx::query(LIST_PERSONS)
+            .bind(&patients)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(|row| OmopPersonSource {
+                key: row.get("key"),
+                gender: row.get("gender"),
+                birth_date: row.get("birth_date"),
+            })
+            .collect();
+
+        let (vocabularies, concept_codes): (Vec<String>, Vec<String>) =
+            source.codes().into_iter().map(|code| (code.vocabulary_id, code.concept_code)).unzip();
+        let mut vocabulary = OmopVocabulary::default();
+        for row in sqlx::query(LIST_CONCEPTS)
+            .bind(&vocabularies)
+            .bind(&concept_codes)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(database_error)?
+        {
+            vocabulary.insert(
+                SourceCode { vocabulary_id: row.get("vocabulary_id"), concept_code: row.get("concept_code") },
+                OmopConcept { concept_id: row.get("concept_id"), standard_concept_id: row.get("standard_concept_id") },
+            );
+        }
+        tx.commit().await.map_err(database_error)?;
+
+        let extract = omop_extract(&source, &vocabulary);
+        tracing::info!(
+            "User {} exported OMOP CDM tables of {} patients, {} source codes mapping to no standard concept, \
+             {} Part 2 records withheld",
+            actor,
+            source.persons.len(),
+            extract.unmapped.len(),
+            withheld
+        );
+        let period = match (query.from, query.to) {
+            (None, None) => "all".to_string(),
+            (from, to) => format!(
+                "{}-{}",
+                from.map(|day| day.format("%Y%m%d").to_string()).unwrap_or_default(),
+                to.map(|day| day.format("%Y%m%d").to_string()).unwrap_or_default()
+            ),
+        };
+        Ok(OmopExport {
+            file_name: format!("omop-cdm-{}.zip", period),
+            bytes: extract.to_zip(),
+        })
+    }
+
+    /// Load the concepts of source codes, replacing those already loaded
+    pub async fn load_concepts(&self, concepts: &[ConceptMapping], actor: Uuid) -> Result<usize, HimsError> {
+        self.require(actor, &[Action::Configure]).await?;
+        if concepts.len() > MAX_CONCEPTS {
+            return Err(validation_error(format!("A request loads at most {} concepts", MAX_CONCEPTS)));
+        }
+        for concept in concepts {
+            check_concept(concept).map_err(validation_error)?;
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        for concept in concepts {
+            sqlx::query(UPSERT_CONCEPT)
+                .bind(concept.vocabulary_id.trim())
+                .bind(concept.concept_code.trim())
+                .bind(concept.concept_id)
+                .bind(concept.standard_concept_id.unwrap_or(0))
+                .bind(&concept.concept_name)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+
+        tracing::info!("User {} loaded {} OMOP concepts", actor, concepts.len());
+        Ok(concepts.len())
+    }
+}
@@ -0,0 +1,111 @@
+//! OMOP Export SQL Queries
+//!
+//! This file contains all SQL queries used by the OMOP export service.
+//! Records are keyed by `reporting_pseudonym()`, so no record ID leaves the
+//! system. `$1` and `$2` are the first and last day exported, NULL for no
+//! bound; records are taken by the UTC day they start. Each record carries its
+//! `meta` and `code` as `resource`, for Part 2 records to be recognised.
+
+/// Patients with a record exported
+pub const LIST_PERSONS: &str = r#"
+    SELECT reporting_pseudonym(p.id) AS key, p.gender, p.birth_date
+    FROM patients p
+    WHERE p.id = ANY($1)
+"#;
+
+/// Encounters that took place
+pub const LIST_VISITS: &str = r#"
+    SELECT e.subject AS patient_id, reporting_pseudonym(e.id) AS key, reporting_pseudonym(e.subject) AS person_key,
+           e.class->>'code' AS class_code, (e.period->>'start')::timestamptz AS start_at,
+           (e.period->>'end')::timestamptz AS end_at, jsonb_build_object('meta', e.meta) AS resource
+    FROM encounters e
+    WHERE e.status NOT IN ('planned', 'cancelled', 'entered-in-error')
+    AND e.period->>'start' IS NOT NULL
+    AND ($1::date IS NULL OR ((e.period->>'start')::timestamptz AT TIME ZONE 'UTC')::date >= $1)
+    AND ($2::date IS NULL OR ((e.period->>'start')::timestamptz AT TIME ZONE 'UTC')::date <= $2)
+"#;
+
+/// Conditions not refuted, from their onset or else when they were recorded
+pub const LIST_CONDITIONS: &str = r#"
+    SELECT c.patient_id, reporting_pseudonym(c.id) AS key, reporting_pseudonym(c.patient_id) AS person_key,
+           reporting_pseudonym(c.encounter_id) AS visit_key, c.code,
+           COALESCE(c.onset_date_time, c.recorded_date) AS start_at, c.abatement_date_time AS end_at,
+           jsonb_build_object('meta', c.meta, 'code', c.code) AS resource
+    FROM conditions c
+    WHERE c.deleted_at IS NULL
+    AND c.verification_status NOT IN ('refuted', 'entered-in-error')
+    AND ($1::date IS NULL OR (COALESCE(c.onset_date_time, c.recorded_date) AT TIME ZONE 'UTC')::date >= $1)
+    AND ($2::date IS NULL OR (COALESCE(c.onset_date_time, c.recorded_date) AT TIME ZONE 'UTC')::date <= $2)
+"#;
+
+/// Medications ordered, coded in the request or else by the medication it
+/// references. The supply is read only where the request gives it in days.
+pub const LIST_DRUGS: &str = r#"
+    SELECT mr.subject AS patient_id, reporting_pseudonym(mr.id) AS key, reporting_pseudonym(mr.subject) AS person_key,
+           reporting_pseudonym(mr.encounter) AS visit_key, COALESCE(mr.medication_codeable_concept, m.code) AS code,
+           mr.authored_on AS start_at,
+           CASE WHEN jsonb_typeof(mr.dispense_request->'quantity'->'value') = 'number'
+                THEN (mr.dispense_request->'quantity'->>'value')::float8 END AS quantity,
+           CASE WHEN jsonb_typeof(mr.dispense_request->'expectedSupplyDuration'->'value') = 'number'
+                AND mr.dispense_request->'expectedSupplyDuration'->>'code' = 'd'
+                THEN round((mr.dispense_request->'expectedSupplyDuration'->>'value')::numeric)::int END AS days_supply,
+           CASE WHEN jsonb_typeof(mr.dispense_request->'numberOfRepeatsAllowed') = 'number'
+                THEN (mr.dispense_request->>'numberOfRepeatsAllowed')::int END AS refills,
+           jsonb_build_object('meta', mr.meta, 'code', COALESCE(mr.medication_codeable_concept, m.code)) AS resource
+    FROM medication_requests mr
+    LEFT JOIN medications m ON m.id = mr.medication_reference
+    WHERE mr.status NOT IN ('cancelled', 'entered-in-error', 'draft')
+    AND mr.intent IN ('order', 'original-order', 'reflex-order', 'filler-order', 'instance-order')
+    AND mr.authored_on IS NOT NULL
+    AND ($1::date IS NULL OR (mr.authored_on AT TIME ZONE 'UTC')::date >= $1)
+    AND ($2::date IS NULL OR (mr.authored_on AT TIME ZONE 'UTC')::date <= $2)
+"#;
+
+/// Observations with a result, their value where it is a quantity
+pub const LIST_OBSERVATIONS: &str = r#"
+    SELECT o.subject AS patient_id, reporting_pseudonym(o.id) AS key, reporting_pseudonym(o.subject) AS person_key,
+           reporting_pseudonym(o.encounter) AS visit_key, o.code, o.at AS start_at,
+           CASE WHEN jsonb_typeof(o.value_quantity->'value') = 'number'
+                THEN (o.value_quantity->>'value')::float8 END AS value,
+           CASE WHEN o.value_quantity->>'system' = 'http://unitsofmeasure.org'
+                THEN o.value_quantity->>'code' END AS unit,
+           jsonb_build_object('meta', o.meta, 'code', o.code) AS resource
+    FROM (
+        SELECT *, COALESCE(effective_datetime, (effective_period->>'start')::timestamptz, issued) AS at
+        FROM observations
+    ) o
+    WHERE o.status IN ('final', 'amended', 'corrected', 'preliminary')
+    AND o.at IS NOT NULL
+    AND ($1::date IS NULL OR (o.at AT TIME ZONE 'UTC')::date >= $1)
+    AND ($2::date IS NULL OR (o.at AT TIME ZONE 'UTC')::date <= $2)
+"#;
+
+/// Vital signs, with the components blood pressure is measured by
+pub const LIST_VITAL_SIGNS: &str = r#"
+    SELECT v.patient_id, reporting_pseudonym(v.id) AS key, reporting_pseudonym(v.patient_id) AS person_key,
+           reporting_pseudonym(v.encounter_id) AS visit_key, v.loinc_code, v.effective_date_time AS start_at,
+           v.value, v.unit, v.component, jsonb_build_object('meta', v.meta) AS resource
+    FROM vital_signs v
+    WHERE v.deleted_at IS NULL
+    AND v.status IN ('final', 'amended', 'corrected', 'preliminary')
+    AND ($1::date IS NULL OR (v.effective_date_time AT TIME ZONE 'UTC')::date >= $1)
+    AND ($2::date IS NULL OR (v.effective_date_time AT TIME ZONE 'UTC')::date <= $2)
+"#;
+
+/// Concepts of the source codes an export carries, `$1` and `$2` their
+/// vocabularies and codes
+pub const LIST_CONCEPTS: &str = r#"
+    SELECT c.vocabulary_id, c.concept_code, c.concept_id, c.standard_concept_id
+    FROM omop_concepts c
+    JOIN unnest($1::text[], $2::text[]) AS s(vocabulary_id, concept_code)
+        ON c.vocabulary_id = s.vocabulary_id AND c.concept_code = s.concept_code
+"#;
+
+/// Load the concept of a source code
+pub const UPSERT_CONCEPT: &str = r#"
+    INSERT INTO omop_concepts (vocabulary_id, concept_code, concept_id, standard_concept_id, concept_name)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (vocabulary_id, concept_code) DO UPDATE
+    SET concept_id = EXCLUDED.concept_id, standard_concept_id = EXCLUDED.standard_concept_id,
+        concept_name = EXCLUDED.concept_name
+"#;
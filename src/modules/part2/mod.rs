@@ -0,0 +1,54 @@
+//! Part 2 Module
+//!
+//! This module segments US substance use disorder records under 42 CFR Part 2 including:
+//! - A registry of patients' Part 2 consents naming recipient, purposes, information and expiry
+//! - Part 2 records recognised by their `ETH` label or ICD-10 substance use diagnosis
+//! - Exports leaving Part 2 records out without a consent covering recipient and purpose
+//! - The prohibition on redisclosure attached to exports including Part 2 records
+
+#[path = "part2.controller.rs"]
+pub mod part2_controller;
+#[path = "part2.segment.rs"]
+pub mod part2_segment;
+#[path = "part2.service.rs"]
+pub mod part2_service;
+#[path = "part2.sql.rs"]
+pub mod part2_sql;
+
+pub use part2_controller::Part2Controller;
+pub use part2_segment::{
+    add_security_labels, is_part2_condition, is_part2_row, is_substance_use_code, no_redisclosure_labels, Part2Disclosure,
+    SegmentedConditions,
+};
+pub use part2_service::{Part2Consent, Part2ConsentRequest, Part2Consents, Part2Service};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Part 2 Module Configuration
+pub struct Part2Module {
+    pub service: Arc<Part2Service>,
+    pub controller: Arc<Part2Controller>,
+}
+
+impl Part2Module {
+    /// Create a new Part 2 Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(Part2Service::new(db_pool));
+        let controller = Arc::new(Part2Controller::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Consent routes, to be merged into the patient routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<Part2Service> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::part2::{Part2Consent, Part2ConsentRequest, Part2Service};
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Records and revokes patients' 42 CFR Part 2 consents
+pub struct Part2Controller {
+    part2_service: Arc<Part2Service>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl Part2Controller {
+    /// Create new controller with injected service
+    pub fn new(part2_service: Arc<Part2Service>) -> Self {
+        Self { part2_service }
+    }
+
+    /// Consent routes, mounted under the patient routes
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/:id/part2-consents", Self::list_consents, "A patient's 42 CFR Part 2 consents, newest first")
+            .post("/:id/part2-consents", Self::record_consent, "Record a signed 42 CFR Part 2 consent")
+            .post(
+                "/:id/part2-consents/:consent_id/revoke",
+                Self::revoke_consent,
+                "Revoke a 42 CFR Part 2 consent",
+            )
+            .with_state(self.part2_service.clone())
+    }
+
+    pub async fn list_consents(
+        State(service): State<Arc<Part2Service>>,
+        headers: HeaderMap,
+        Path(patient_id): Path<Uuid>,
+    ) -> Result<Json<Vec<Part2Consent>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        service
+            .list_consents(actor, patient_id)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list Part 2 consents", e))
+    }
+
+    pub async fn record_consent(
+        State(service): State<Arc<Part2Service>>,
+        headers: HeaderMap,
+        Path(patient_id): Path<Uuid>,
+        Json(request): Json<Part2ConsentRequest>,
+    ) -> Result<(StatusCode, Json<Part2Consent>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        service
+            .record_consent(actor, patient_id, request)
+            .await
+            .map(|consent| (StatusCode::CREATED, Json(consent)))
+            .map_err(|e| Self::error_response("Failed to record Part 2 consent", e))
+    }
+
+    pub async fn revoke_consent(
+        State(service): State<Arc<Part2Service>>,
+        headers: HeaderMap,
+        Path((patient_id, consent_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<Json<Part2Consent>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.revoke_consent(actor, patient_id, consent_id).await {
+            Ok(Some(consent)) => Ok(Json(consent)),
+            Ok(None) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Not found".to_string(),
+                    message: format!("Patient {} has no Part 2 consent {} in force", patient_id, consent_id),
+                }),
+            )),
+            Err(e) => Err(Self::error_response("Failed to revoke Part 2 consent", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,230 @@
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{
+    ACT_CODE_SYSTEM, CONFIDENTIALITY_SYSTEM, ICD_10_CM_SYSTEM, ICD_10_SYSTEM, NO_REDISCLOSURE_SECURITY_CODE,
+    RESTRICTED_CONFIDENTIALITY_CODE,
+};
+use crate::models::{Coding, Condition};
+use crate::modules::authorization::ConfidentialityLabel;
+
+/// Whether a disclosure of a patient's records may include their 42 CFR
+/// Part 2 records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Part2Disclosure {
+    pub patient_id: Uuid,
+    /// The patient is labelled a substance use patient, which makes all
+    /// their records Part 2 records
+    pub patient_labelled: bool,
+    /// A consent in force covers the recipient and purpose
+    pub consented: bool,
+}
+
+/// Conditions segmented for a disclosure
+#[derive(Debug, Clone)]
+pub struct SegmentedConditions {
+    pub conditions: Vec<Condition>,
+    /// Part 2 conditions left out for want of consent
+    pub withheld: usize,
+    /// Part 2 conditions are included, so the disclosure must carry the
+    /// notice against redisclosure
+    pub part2: bool,
+}
+
+/// ICD-10 substance use disorders, F10-F19 less nicotine (F17), which
+/// Part 2 leaves out
+pub fn is_substance_use_code(system: &str, code: &str) -> bool {
+    if system != ICD_10_SYSTEM && system != ICD_10_CM_SYSTEM {
+        return false;
+    }
+    let code = code.trim().to_ascii_uppercase();
+    code.len() >= 3 && code.starts_with("F1") && code.as_bytes()[2].is_ascii_digit() && !code.starts_with("F17")
+}
+
+/// Whether the condition is a Part 2 record: labelled substance use or
+/// coded as a substance use disorder
+pub fn is_part2_condition(condition: &Condition) -> bool {
+    let labelled = condition.meta.security.iter().any(|coding| {
+        coding.system.as_deref() == Some(ACT_CODE_SYSTEM)
+            && coding.code.as_deref() == Some(ConfidentialityLabel::SubstanceAbuse.code())
+    });
+    labelled
+        || condition.code.coding.iter().any(|coding| match (&coding.system, &coding.code) {
+            (Some(system), Some(code)) => is_substance_use_code(system, code),
+            _ => false,
+        })
+}
+
+/// Whether a stored resource row, as resource history keeps it, is a Part 2
+/// record: labelled substance use in `meta.security` or coded as a
+/// substance use disorder
+pub fn is_part2_row(resource: &Value) -> bool {
+    let labelled = resource["meta"]["security"].as_array().into_iter().flatten().any(|coding| {
+        coding["system"] == ACT_CODE_SYSTEM && coding["code"] == ConfidentialityLabel::SubstanceAbuse.code()
+    });
+    labelled
+        || resource["code"]["coding"].as_array().into_iter().flatten().any(|coding| {
+            match (coding["system"].as_str(), coding["code"].as_str()) {
+                (Some(system), Some(code)) => is_substance_use_code(system, code),
+                _ => false,
+            }
+        })
+}
+
+/// Security labels of a disclosure including Part 2 records: restricted,
+/// and not to be redisclosed without the patient's consent
+pub fn no_redisclosure_labels() -> Vec<Coding> {
+    vec![
+        coding(CONFIDENTIALITY_SYSTEM, RESTRICTED_CONFIDENTIALITY_CODE, "restricted"),
+        coding(
+            ACT_CODE_SYSTEM,
+            NO_REDISCLOSURE_SECURITY_CODE,
+            "prohibition on redisclosure without patient consent directive",
+        ),
+    ]
+}
+
+/// Add `labels` to `security`, skipping those already present
+pub fn add_security_labels(security: &mut Vec<Coding>, labels: Vec<Coding>) {
+    for label in labels {
+        if !security.iter().any(|coding| coding.system == label.system && coding.code == label.code) {
+            security.push(label);
+        }
+    }
+}
+
+fn coding(system: &str, code: &str, display: &str) -> Coding {
+    Coding {
+        system: Some(system.to_string()),
+        version: None,
+        code: Some(code.to_string()),
+        display: Some(display.to_string()),
+    }
+}
+
+impl Part2Disclosure {
+    /// Whether a record of the patient may be disclosed: Part 2 records,
+    /// and every record of a labelled patient, only under consent
+    pub fn discloses(&self, part2_record: bool) -> bool {
+        self.consented || (!self.patient_labelled && !part2_record)
+    }
+
+    /// Leave the Part 2 conditions out of a disclosure without consent, and
+    /// label those disclosed with consent. The records of a labelled patient
+    /// are not disclosed at all without consent, since even their problems
+    /// unrelated to substance use would reveal them as a Part 2 patient.
+    pub fn segment(&self, conditions: Vec<Condition>) -> Result<SegmentedConditions, HimsError> {
+        if self.patient_labelled && !self.consented {
+            return Err(HimsError::SecurityError {
+                message: format!(
+                    "Records of patient {} are protected by 42 CFR Part 2; disclosing them needs the patient's consent",
+                    self.patient_id
+                ),
+            });
+        }
+
+        let mut segmented = SegmentedConditions {
+            conditions: Vec::with_capacity(conditions.len()),
+            withheld: 0,
+            part2: false,
+        };
+        for mut condition in conditions {
+            if !self.patient_labelled && !is_part2_condition(&condition) {
+                segmented.conditions.push(condition);
+            } else if self.consented {
+                let mut labels = vec![coding(
+                    ACT_CODE_SYSTEM,
+                    ConfidentialityLabel::SubstanceAbuse.code(),
+                    ConfidentialityLabel::SubstanceAbuse.display(),
+                )];
+                labels.extend(no_redisclosure_labels());
+                add_security_labels(&mut condition.meta.security, labels);
+                segmented.conditions.push(condition);
+                segmented.part2 = true;
+            } else {
+                segmented.withheld += 1;
+            }
+        }
+        Ok(segmented)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::constants::SNOMED_CT_SYSTEM;
+    use crate::models::{CodeableConcept, ConditionCategory};
+
+    fn condition(system: &str, code: &str) -> Condition {
+        let code = CodeableConcept {
+            coding: vec![coding(system, code, code)],
+            text: None,
+        };
+        Condition::new(Uuid::new_v4(), code, ConditionCategory::ProblemListItem)
+    }
+
+    fn disclosure(patient_labelled: bool, consented: bool) -> Part2Disclosure {
+        Part2Disclosure {
+            patient_id: Uuid::new_v4(),
+            patient_labelled,
+            consented,
+        }
+    }
+
+    #[test]
+    fn test_is_part2_condition() {
+        assert!(is_part2_condition(&condition(ICD_10_CM_SYSTEM, "F10.20")));
+        assert!(is_part2_condition(&condition(ICD_10_SYSTEM, "f11")));
+        assert!(!is_part2_condition(&condition(ICD_10_CM_SYSTEM, "F17.210")));
+        assert!(!is_part2_condition(&condition(ICD_10_CM_SYSTEM, "F20.9")));
+        assert!(!is_part2_condition(&condition(SNOMED_CT_SYSTEM, "F10")));
+
+        let mut labelled = condition(SNOMED_CT_SYSTEM, "73211009");
+        labelled.meta.security.push(coding(ACT_CODE_SYSTEM, "ETH", "substance abuse"));
+        assert!(is_part2_condition(&labelled));
+    }
+
+    #[test]
+    fn test_is_part2_row() {
+        let coded = serde_json::to_value(condition(ICD_10_CM_SYSTEM, "F10.20")).unwrap();
+        let diabetes = serde_json::to_value(condition(SNOMED_CT_SYSTEM, "73211009")).unwrap();
+        assert!(is_part2_row(&coded));
+        assert!(!is_part2_row(&diabetes));
+
+        let mut labelled = diabetes;
+        labelled["meta"]["security"] = serde_json::json!([{ "system": ACT_CODE_SYSTEM, "code": "ETH" }]);
+        assert!(is_part2_row(&labelled));
+
+        assert!(disclosure(false, false).discloses(false));
+        assert!(!disclosure(false, false).discloses(true));
+        assert!(!disclosure(true, false).discloses(false));
+        assert!(disclosure(true, true).discloses(true));
+    }
+
+    #[test]
+    fn test_segment_conditions() {
+        let diabetes = condition(SNOMED_CT_SYSTEM, "73211009");
+        let alcohol = condition(ICD_10_CM_SYSTEM, "F10.20");
+
+        let segmented = disclosure(false, false).segment(vec![diabetes.clone(), alcohol.clone()]).unwrap();
+        assert_eq!(segmented.conditions.len(), 1);
+        assert_eq!(segmented.conditions[0].id, diabetes.id);
+        assert_eq!(segmented.withheld, 1);
+        assert!(!segmented.part2);
+
+        let segmented = disclosure(false, true).segment(vec![diabetes.clone(), alcohol.clone()]).unwrap();
+        assert_eq!(segmented.conditions.len(), 2);
+        assert!(segmented.part2);
+        assert!(segmented.conditions[0].meta.security.is_empty());
+        let codes: Vec<_> = segmented.conditions[1].meta.security.iter().filter_map(|c| c.code.as_deref()).collect();
+        assert_eq!(codes, vec!["ETH", "R", "NORDSCLCD"]);
+
+        assert!(matches!(
+            disclosure(true, false).segment(vec![diabetes.clone()]),
+            Err(HimsError::SecurityError { .. })
+        ));
+        let segmented = disclosure(true, true).segment(vec![diabetes]).unwrap();
+        assert!(segmented.part2);
+    }
+}
@@ -0,0 +1,320 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::{
+    Action, AuthResult, ConfidentialityLabel, ConsentSource, PurposeOfUse, Resource, SecurityLabelSource, Subject,
+};
+use crate::modules::part2::part2_segment::Part2Disclosure;
+use crate::modules::part2::part2_sql;
+use crate::modules::role::RoleService;
+use crate::modules::security_label::RecordSecurityLabels;
+
+/// A patient's consent to disclose their substance use disorder records
+#[derive(Debug, Clone, Serialize)]
+pub struct Part2Consent {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// Who may receive the records; `None` for a general designation
+    pub recipient: Option<String>,
+    pub purposes: Vec<PurposeOfUse>,
+    /// The amount and kind of information that may be disclosed
+    pub information: String,
+    /// The patient or their authorized representative
+    pub signed_by: String,
+    pub signed_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub recorded_by: Uuid,
+    pub recorded_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Part2ConsentRequest {
+    pub recipient: Option<String>,
+    /// Purpose names or HL7 codes, e.g. `treatment` or `HPAYMT`
+    pub purposes: Vec<String>,
+    pub information: String,
+    pub signed_by: String,
+    /// When the consent was signed; now if not given
+    pub signed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Part 2 consent registry deciding what substance use records a
+/// disclosure may include
+#[derive(Debug, Clone)]
+pub struct Part2Consents {
+    pool: PgPool,
+    labels: RecordSecurityLabels,
+}
+
+impl Part2Consents {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            labels: RecordSecurityLabels::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Whether a consent in force lets the patient's records reach
+    /// `recipient` for `purpose`. Without a recipient, only consents
+    /// naming none apply.
+    pub async fn has_consent(
+        &self,
+        patient_id: Uuid,
+        recipient: Option<&str>,
+        purpose: PurposeOfUse,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(part2_sql::HAS_CONSENT)
+            .bind(patient_id)
+            .bind(recipient)
+            .bind(purpose.code())
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// What a disclosure of the patient's records to `recipient` for
+    /// `purpose` may include. No purpose means no consent applies.
+    pub async fn disclosure(
+        &self,
+        patient_id: Uuid,
+        recipient: Option<&str>,
+        purpose: Option<PurposeOfUse>,
+    ) -> Result<Part2Disclosure, HimsError> {
+        let labels = self
+            .labels
+            .security_labels(&Resource::Patient(patient_id), None)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let consented = match purpose {
+            Some(purpose) => self.has_consent(patient_id, recipient, purpose).await.map_err(database_error)?,
+            None => false,
+        };
+
+        Ok(Part2Disclosure {
+            patient_id,
+            patient_labelled: labels.contains(&ConfidentialityLabel::SubstanceAbuse),
+            consented,
+        })
+    }
+
+    /// What a disclosure to `recipient` for `purpose` may include of each
+    /// patient's records, looking the patients' labels and consents up
+    /// together for exports spanning many patients
+    pub async fn disclosures(
+        &self,
+        patient_ids: &[Uuid],
+        recipient: &Subject,
+        purpose: PurposeOfUse,
+    ) -> Result<HashMap<Uuid, Part2Disclosure>, HimsError> {
+        let resources: Vec<(Resource, Option<Uuid>)> =
+            patient_ids.iter().map(|patient_id| (Resource::Patient(*patient_id), None)).collect();
+        let labels = self
+            .labels
+            .security_labels_batch(&resources)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let requests: Vec<(Uuid, Subject, PurposeOfUse)> =
+            patient_ids.iter().map(|patient_id| (*patient_id, recipient.clone(), purpose)).collect();
+        let consents = self
+            .part2_consent_batch(&requests)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(patient_ids
+            .iter()
+            .zip(labels)
+            .zip(consents)
+            .map(|((patient_id, labels), consented)| {
+                let disclosure = Part2Disclosure {
+                    patient_id: *patient_id,
+                    patient_labelled: labels.contains(&ConfidentialityLabel::SubstanceAbuse),
+                    consented,
+                };
+                (*patient_id, disclosure)
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ConsentSource for Part2Consents {
+    async fn part2_consent(&self, patient_id: Uuid, recipient: &Subject, purpose: PurposeOfUse) -> AuthResult<bool> {
+        Ok(self.has_consent(patient_id, Some(&recipient.to_string()), purpose).await?)
+    }
+
+    async fn part2_consent_batch(&self, disclosures: &[(Uuid, Subject, PurposeOfUse)]) -> AuthResult<Vec<bool>> {
+        if disclosures.is_empty() {
+            return Ok(Vec::new());
+        }
+        let patient_ids: Vec<Uuid> = disclosures.iter().map(|(patient_id, _, _)| *patient_id).collect();
+        let recipients: Vec<String> = disclosures.iter().map(|(_, recipient, _)| recipient.to_string()).collect();
+        let purposes: Vec<&str> = disclosures.iter().map(|(_, _, purpose)| purpose.code()).collect();
+
+        let covered: Vec<i64> = sqlx::query_scalar(part2_sql::HAS_CONSENTS)
+            .bind(&patient_ids)
+            .bind(&recipients)
+            .bind(&purposes)
+            .fetch_all(&self.pool)
+            .await?;
+
+        // Ordinality is 1-based
+        let mut consents = vec![false; disclosures.len()];
+        for ordinal in covered {
+            if let Some(consent) = consents.get_mut((ordinal - 1) as usize) {
+                *consent = true;
+            }
+        }
+        Ok(consents)
+    }
+}
+
+/// Part 2 service recording and revoking consents
+pub struct Part2Service {
+    pool: PgPool,
+    role_service: RoleService,
+}
+
+impl Part2Service {
+    /// Create new Part 2 service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Record a signed consent
+    pub async fn record_consent(
+        &self,
+        actor: Uuid,
+        patient_id: Uuid,
+        request: Part2ConsentRequest,
+    ) -> Result<Part2Consent, HimsError> {
+        self.require(actor, Action::Write).await?;
+
+        let purposes = request
+            .purposes
+            .iter()
+            .map(|purpose| {
+                PurposeOfUse::parse(purpose).ok_or_else(|| HimsError::ValidationError {
+                    message: format!("Unknown purpose of use: {}", purpose),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if purposes.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "A Part 2 consent names at least one purpose".to_string(),
+            });
+        }
+        if request.information.trim().is_empty() || request.signed_by.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "A Part 2 consent states the information disclosed and who signed it".to_string(),
+            });
+        }
+        let signed_at = request.signed_at.unwrap_or_else(Utc::now);
+        if request.expires_at <= signed_at {
+            return Err(HimsError::ValidationError {
+                message: "A Part 2 consent expires after it is signed".to_string(),
+            });
+        }
+
+        let codes: Vec<&str> = purposes.iter().map(|purpose| purpose.code()).collect();
+        let row = sqlx::query(part2_sql::INSERT_CONSENT)
+            .bind(patient_id)
+            .bind(request.recipient.as_deref().map(str::trim).filter(|recipient| !recipient.is_empty()))
+            .bind(&codes)
+            .bind(request.information.trim())
+            .bind(request.signed_by.trim())
+            .bind(signed_at)
+            .bind(request.expires_at)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        let consent = consent_from_row(&row).map_err(database_error)?;
+        tracing::info!("User {} recorded Part 2 consent {} of patient {}", actor, consent.id, patient_id);
+        Ok(consent)
+    }
+
+    /// Consents of a patient, newest first, including revoked and expired ones
+    pub async fn list_consents(&self, actor: Uuid, patient_id: Uuid) -> Result<Vec<Part2Consent>, HimsError> {
+        self.require(actor, Action::Read).await?;
+
+        let rows = sqlx::query(part2_sql::LIST_CONSENTS)
+            .bind(patient_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        rows.iter()
+            .map(consent_from_row)
+            .collect::<Result<_, _>>()
+            .map_err(database_error)
+    }
+
+    /// Revoke a consent; disclosures already made stand. `None` when the
+    /// patient has no such consent in force.
+    pub async fn revoke_consent(
+        &self,
+        actor: Uuid,
+        patient_id: Uuid,
+        consent_id: Uuid,
+    ) -> Result<Option<Part2Consent>, HimsError> {
+        self.require(actor, Action::Write).await?;
+
+        let row = sqlx::query(part2_sql::REVOKE_CONSENT)
+            .bind(patient_id)
+            .bind(consent_id)
+            .bind(actor)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        tracing::info!("User {} revoked Part 2 consent {} of patient {}", actor, consent_id, patient_id);
+        consent_from_row(&row).map(Some).map_err(database_error)
+    }
+
+    /// Require a permission of the actor
+    async fn require(&self, actor: Uuid, action: Action) -> Result<(), HimsError> {
+        if !self.role_service.get_user_permissions(actor).await?.contains(&action) {
+            tracing::warn!("User {} lacks {} permission", actor, action);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", action),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn consent_from_row(row: &PgRow) -> Result<Part2Consent, sqlx::Error> {
+    let purposes: Vec<String> = row.try_get("purposes")?;
+    Ok(Part2Consent {
+        id: row.try_get("id")?,
+        patient_id: row.try_get("patient_id")?,
+        recipient: row.try_get("recipient")?,
+        purposes: purposes.iter().filter_map(|purpose| PurposeOfUse::parse(purpose)).collect(),
+        information: row.try_get("information")?,
+        signed_by: row.try_get("signed_by")?,
+        signed_at: row.try_get("signed_at")?,
+        expires_at: row.try_get("expires_at")?,
+        recorded_by: row.try_get("recorded_by")?,
+        recorded_at: row.try_get("recorded_at")?,
+        revoked_at: row.try_get("revoked_at")?,
+        revoked_by: row.try_get("revoked_by")?,
+    })
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
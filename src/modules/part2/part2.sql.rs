@@ -0,0 +1,63 @@
+//! Part 2 SQL Queries
+//!
+//! This file contains all SQL queries used by the Part 2 consent service.
+
+/// Record a consent
+pub const INSERT_CONSENT: &str = r#"
+    INSERT INTO part2_consents (
+        patient_id, recipient, purposes, information, signed_by, signed_at, expires_at, recorded_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING id, patient_id, recipient, purposes, information, signed_by, signed_at, expires_at,
+        recorded_by, recorded_at, revoked_at, revoked_by
+"#;
+
+/// Consents of patient $1, newest first
+pub const LIST_CONSENTS: &str = r#"
+    SELECT id, patient_id, recipient, purposes, information, signed_by, signed_at, expires_at,
+        recorded_by, recorded_at, revoked_at, revoked_by
+    FROM part2_consents
+    WHERE patient_id = $1
+    ORDER BY signed_at DESC, id
+"#;
+
+/// Revoke consent $2 of patient $1 by user $3
+pub const REVOKE_CONSENT: &str = r#"
+    UPDATE part2_consents
+    SET revoked_at = NOW(), revoked_by = $3
+    WHERE patient_id = $1 AND id = $2 AND revoked_at IS NULL
+    RETURNING id, patient_id, recipient, purposes, information, signed_by, signed_at, expires_at,
+        recorded_by, recorded_at, revoked_at, revoked_by
+"#;
+
+/// Whether a consent of patient $1 in force covers recipient $2 and
+/// purpose $3. Consents without a recipient cover every recipient.
+pub const HAS_CONSENT: &str = r#"
+    SELECT EXISTS (
+        SELECT 1
+        FROM part2_consents
+        WHERE patient_id = $1
+          AND revoked_at IS NULL
+          AND signed_at <= NOW()
+          AND expires_at > NOW()
+          AND $3 = ANY(purposes)
+          AND (recipient IS NULL OR lower(recipient) = lower($2))
+    )
+"#;
+
+/// Which of the disclosures of patients $1 to recipients $2 for purposes
+/// $3 a consent in force covers, by position in the arrays
+pub const HAS_CONSENTS: &str = r#"
+    SELECT d.ordinal
+    FROM UNNEST($1::uuid[], $2::text[], $3::text[])
+        WITH ORDINALITY AS d(patient_id, recipient, purpose, ordinal)
+    WHERE EXISTS (
+        SELECT 1
+        FROM part2_consents c
+        WHERE c.patient_id = d.patient_id
+          AND c.revoked_at IS NULL
+          AND c.signed_at <= NOW()
+          AND c.expires_at > NOW()
+          AND d.purpose = ANY(c.purposes)
+          AND (c.recipient IS NULL OR lower(c.recipient) = lower(d.recipient))
+    )
+"#;
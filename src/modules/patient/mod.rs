@@ -2,6 +2,9 @@
 //! 
 //! This module provides patient management functionality including:
 //! - Patient CRUD operations
+//! - FHIR search (name, identifier, birthdate, gender, address-city, _sort,
+//!   _total, _revinclude)
+//! - Conditional create/update/delete keyed on identifiers
 //! - FHIR R4 compliance
 //! - Audit logging
 //! - Healthcare data validation
@@ -14,12 +17,18 @@ pub mod patient_service;
 pub mod patient_sql;
 
 pub use patient_controller::PatientController;
-pub use patient_service::PatientService;
+pub use patient_service::{
+    ConditionalCreate, ConditionalUpdate, PatientRevInclude, PatientSearch, PatientSearchResult, PatientService,
+};
 
-use axum::Router;
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::events::EventBus;
+use crate::standards::fhir::PackValidator;
+use crate::utils::api_router::ApiRouter;
+
 /// Patient Module Configuration
 pub struct PatientModule {
     pub service: Arc<PatientService>,
@@ -27,10 +36,16 @@ pub struct PatientModule {
 }
 
 impl PatientModule {
-    /// Create a new Patient Module with simplified setup
-    pub fn new(db_pool: PgPool) -> Self {
-        let service = Arc::new(PatientService::new(db_pool));
-        let controller = Arc::new(PatientController::new_simple(service.clone()));
+    /// Create a new Patient Module with dependency injection; patients are
+    /// validated against `profiles` when given
+    pub fn new(
+        db_pool: PgPool,
+        events: EventBus,
+        profiles: Option<PackValidator>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        let service = Arc::new(PatientService::with_events(db_pool, events).with_profiles(profiles));
+        let controller = Arc::new(PatientController::new(service.clone(), authorization_engine));
         
         Self {
             service,
@@ -39,7 +54,7 @@ impl PatientModule {
     }
 
     /// Register routes for this module
-    pub fn routes(&self) -> Router {
+    pub fn routes(&self) -> ApiRouter {
         self.controller.clone().routes()
     }
 
@@ -1,25 +1,36 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::{StatusCode, HeaderMap},
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, StatusCode, HeaderMap, HeaderName},
     response::Json,
-    routing::{delete, get, post, put},
-    Router,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use crate::models::{
     Patient, ResourceMeta, HumanName, ContactPoint, Gender, Address, 
-    CodeableConcept, PatientContact, PatientCommunication
+    CodeableConcept, PatientContact, PatientCommunication, Identifier
 };
+use crate::modules::auth::AuthContext;
 use crate::modules::patient::PatientService;
+use crate::modules::patient::patient_service::{ConditionalCreate, ConditionalUpdate, PatientSearch, PatientSearchResult};
 use crate::modules::authorization::{
     HimsAuthorizationEngine, AuthorizationEngine, AuthorizationRequest, AuthorizationResponse,
-    Subject, Resource, Action, AccessDecision, SessionContext,
+    Subject, Resource, Action, AccessDecision, SessionContext, Consistency,
 };
-use crate::utils::auth::{extract_user_from_headers, get_user_session_context};
+use crate::core::HimsError;
+use crate::exporters::abha::AbhaCard;
+use crate::models::constants::ABHA_NUMBER_SYSTEM;
+use crate::utils::api_router::ApiRouter;
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::utils::auth::get_user_session_context;
+use crate::utils::etag::{etag, if_match_version, precondition_status, version_of, versioned, Versioned};
+use crate::utils::fhir_search::{parse_search_query, EntrySearch, IncludedResource, SearchEntryMode, TokenParam};
+use crate::utils::pagination::{page_links, BundleLink};
+
+/// Header carrying conditional create criteria, e.g. `identifier=system|value`
+pub const IF_NONE_EXIST: &str = "if-none-exist";
 
 /// Patient controller for FHIR R4 compliant patient management with authorization
 pub struct PatientController {
@@ -27,18 +38,10 @@ pub struct PatientController {
     authorization_engine: Arc<HimsAuthorizationEngine>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct PatientQuery {
-    pub active: Option<bool>,
-    pub gender: Option<String>,
-    pub name: Option<String>,
-    pub identifier: Option<String>,
-    pub _count: Option<u32>,
-    pub _offset: Option<u32>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PatientCreateRequest {
+    #[serde(default)]
+    pub identifier: Vec<Identifier>,
     pub name: Vec<HumanName>,
     pub telecom: Vec<ContactPoint>,
     pub gender: Gender,
@@ -49,35 +52,64 @@ pub struct PatientCreateRequest {
     pub communication: Vec<PatientCommunication>,
 }
 
+/// A scanned ABHA card QR code
+#[derive(Debug, Deserialize)]
+pub struct AbhaScanRequest {
+    pub payload: String,
+}
+
+/// What a scanned ABHA card registers: the patient to create, and the
+/// `If-None-Exist` criteria that match them if already registered
+#[derive(Debug, Serialize)]
+pub struct AbhaScanResponse {
+    pub card: AbhaCard,
+    pub patient: PatientCreateRequest,
+    pub if_none_exist: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PatientResponse {
-    pub resourceType: String,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
     pub id: Uuid,
     pub meta: ResourceMeta,
+    pub identifier: Vec<Identifier>,
     pub active: bool,
     pub name: Vec<HumanName>,
     pub telecom: Vec<ContactPoint>,
     pub gender: Gender,
-    pub birthDate: Option<chrono::NaiveDate>,
+    #[serde(rename = "birthDate")]
+    pub birth_date: Option<chrono::NaiveDate>,
     pub address: Vec<Address>,
-    pub maritalStatus: Option<CodeableConcept>,
+    #[serde(rename = "maritalStatus")]
+    pub marital_status: Option<CodeableConcept>,
     pub contact: Vec<PatientContact>,
     pub communication: Vec<PatientCommunication>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct PatientBundle {
-    pub resourceType: String,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
     pub id: Uuid,
     pub meta: ResourceMeta,
-    pub total: u32,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    pub link: Vec<BundleLink>,
     pub entry: Vec<PatientBundleEntry>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct PatientBundleEntry {
-    pub fullUrl: String,
-    pub resource: PatientResponse,
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    /// A patient, or a resource added by `_revinclude`
+    pub resource: serde_json::Value,
+    pub search: EntrySearch,
 }
 
 impl PatientController {
@@ -92,69 +124,16 @@ impl PatientController {
         }
     }
 
-    /// Create new controller with just patient service (for backwards compatibility)
-    pub fn new_simple(patient_service: Arc<PatientService>) -> Self {
-        // Create a dummy authorization engine for now
-        // TODO: Remove this once full integration is complete
-        use crate::modules::authorization::{
-            PostgresAuthorizationStorage, HimsPolicyEngine, AuditManager, AuthorizationConfig
-        };
-        
-        // This is a temporary workaround - we'll need proper dependency injection
-        let dummy_storage = Arc::new(PostgresAuthorizationStorage::new(patient_service.get_db_pool()));
-        let dummy_policy_engine = Arc::new(HimsPolicyEngine::new());
-        
-        // Create a basic audit config
-        use crate::modules::authorization::AuditConfig;
-        let audit_config = AuditConfig {
-            enabled: true,
-            log_all_decisions: true,
-            log_emergency_access: true,
-            log_break_glass: true,
-            log_policy_evaluations: true,
-            log_relationship_checks: true,
-            retention_days: 365,
-            real_time_compliance: true,
-            alert_on_suspicious: true,
-        };
-        let dummy_audit_manager = Arc::new(AuditManager::new(audit_config));
-        let dummy_config = AuthorizationConfig::default();
-        
-        let authorization_engine = Arc::new(HimsAuthorizationEngine::new(
-            dummy_storage,
-            dummy_policy_engine,
-            dummy_audit_manager,
-            dummy_config,
-        ));
-        
-        Self { 
-            patient_service,
-            authorization_engine,
-        }
-    }
-
-    /// Check authorization for a given request
+    /// Ask the authorization engine whether the user may take `action` on
+    /// `resource`, for the purpose of use the request declares
     async fn check_authorization(
         &self,
+        auth: &AuthContext,
         headers: &HeaderMap,
         action: Action,
         resource: Resource,
     ) -> Result<AuthorizationResponse, (StatusCode, Json<ErrorResponse>)> {
-        // Extract user from headers (JWT token, session, etc.)
-        let user_id = extract_user_from_headers(headers)
-            .map_err(|e| {
-                tracing::error!("Failed to extract user from headers: {}", e);
-                (
-                    StatusCode::UNAUTHORIZED,
-                    Json(ErrorResponse {
-                        error: "Unauthorized".to_string(),
-                        message: "Invalid or missing authentication".to_string(),
-                    }),
-                )
-            })?;
-
-        // Get user session context
-        let context = get_user_session_context(user_id, headers)
+        let context = get_user_session_context(auth, headers)
             .await
             .map_err(|e| {
                 tracing::error!("Failed to get user session context: {}", e);
@@ -167,41 +146,28 @@ impl PatientController {
                 )
             })?;
         
-        // Extract session information for SessionContext
-        let session_id = headers.get("x-session-id")
-            .and_then(|h| h.to_str().ok())
-            .unwrap_or("unknown")
-            .to_string();
-        
-        let ip_address = headers.get("x-forwarded-for")
-            .or_else(|| headers.get("x-real-ip"))
-            .and_then(|h| h.to_str().ok())
-            .map(|s| s.to_string());
-        
-        let user_agent = headers.get("user-agent")
-            .and_then(|h| h.to_str().ok())
-            .map(|s| s.to_string());
-        
         let session = SessionContext {
-            user_id,
-            session_id,
-            ip_address,
-            user_agent,
+            user_id: auth.user_id,
+            session_id: auth.session_id.clone().unwrap_or_else(|| "unknown".to_string()),
+            ip_address: auth.source_ip.clone(),
+            user_agent: auth.user_agent.clone(),
             department_id: None,
             location_id: None,
             shift_id: None,
             mfa_verified: false,
+            mfa_verified_at: None,
             risk_score: 0.0,
         };
 
         // Create authorization request
         let auth_request = AuthorizationRequest {
-            subject: Subject::User(user_id),
+            subject: Subject::User(auth.user_id),
             action,
             resource,
             context,
             session,
             request_id: Some(Uuid::new_v4().to_string()),
+            consistency: Consistency::default(),
         };
 
         // Check authorization
@@ -220,6 +186,77 @@ impl PatientController {
             })
     }
 
+    /// Fail with 403 unless the engine allows `action` on `resource`
+    async fn authorize(
+        &self,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        action: Action,
+        resource: Resource,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let auth_response = self.check_authorization(auth, headers, action, resource).await?;
+        Self::handle_authorization_response(&auth_response)
+    }
+
+    /// The patients of a search page the user may read, with only the
+    /// included resources that refer to them. Included records also need
+    /// read access of their own, as labelled records may be restricted.
+    async fn readable(
+        &self,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        mut result: PatientSearchResult,
+    ) -> Result<PatientSearchResult, (StatusCode, Json<ErrorResponse>)> {
+        let authorization_error = |e: HimsError| {
+            tracing::error!("Authorization check failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Authorization error".to_string(),
+                    message: "Failed to check authorization".to_string(),
+                }),
+            )
+        };
+        let context = get_user_session_context(auth, headers).await.map_err(|e| {
+            tracing::error!("Failed to get user session context: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Internal server error".to_string(),
+                    message: "Failed to establish session context".to_string(),
+                }),
+            )
+        })?;
+        let authorizer = FieldAuthorizer::for_request(self.authorization_engine.clone(), auth, context);
+
+        let found = result.patients.len();
+        result.patients = authorizer
+            .retain(Action::Read, result.patients, |patient| Resource::Patient(patient.id))
+            .await
+            .map_err(authorization_error)?;
+        if result.patients.len() < found {
+            let withheld = found - result.patients.len();
+            tracing::info!("Withheld {} of {} patients from user {}", withheld, found, auth.user_id);
+        }
+
+        let readable: HashSet<Uuid> = result.patients.iter().map(|patient| patient.id).collect();
+        let (records, others): (Vec<_>, Vec<_>) = std::mem::take(&mut result.included)
+            .into_iter()
+            .filter(|included| Self::included_patient(included).is_some_and(|id| readable.contains(&id)))
+            .partition(|included| included.resource_type == "DocumentReference");
+        result.included = authorizer
+            .retain(Action::Read, records, |record| Resource::MedicalRecord(record.id))
+            .await
+            .map_err(authorization_error)?;
+        result.included.extend(others);
+        Ok(result)
+    }
+
+    /// Patient an `_revinclude`d resource refers to
+    fn included_patient(included: &IncludedResource) -> Option<Uuid> {
+        included.resource.get("patient_id")?.as_str()?.parse().ok()
+    }
+
     /// Handle authorization response
     fn handle_authorization_response(
         auth_response: &AuthorizationResponse,
@@ -265,32 +302,78 @@ impl PatientController {
     }
 
     /// Create router with dependency injection
-    pub fn routes(self: Arc<Self>) -> Router {
-        Router::new()
-            .route("/", post(Self::create_patient))
-            .route("/", get(Self::search_patients))
-            .route("/:id", get(Self::get_patient))
-            .route("/:id", put(Self::update_patient))
-            .route("/:id", delete(Self::delete_patient))
+    pub fn routes(self: Arc<Self>) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_patient, "Create new patient, or match an existing one with If-None-Exist")
+            .get("/", Self::search_patients, "Search patients with FHIR query parameters")
+            .put("/", Self::conditional_update_patient, "Conditional update by search criteria")
+            .delete("/", Self::conditional_delete_patient, "Conditional delete by search criteria")
+            .post("/abha-scan", Self::scan_abha_card, "Read a scanned ABHA card QR code into a patient to register")
+            .get("/:id", Self::get_patient, "Get patient by ID with authorization")
+            .get("/:id/abha-qr", Self::abha_qr_code, "Get a patient's ABHA card QR code as SVG")
+            .put("/:id", Self::update_patient, "Update patient")
+            .delete("/:id", Self::delete_patient, "Delete patient (soft delete)")
             .with_state(self)
     }
 
-    /// Create new patient
+    /// Create new patient. With `If-None-Exist`, an existing patient
+    /// matching its criteria is returned instead (200) and nothing is created.
     pub async fn create_patient(
         State(controller): State<Arc<PatientController>>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Json(payload): Json<PatientCreateRequest>,
-    ) -> Result<(StatusCode, Json<PatientResponse>), (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<(StatusCode, [(HeaderName, String); 2], Json<PatientResponse>), (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Creating new patient");
         
-        match controller.patient_service.create_patient(payload).await {
-            Ok(patient) => {
+        let criteria = match headers.get(IF_NONE_EXIST) {
+            Some(value) => {
+                let query = value.to_str().map_err(|_| {
+                    Self::bad_request("Invalid If-None-Exist", "If-None-Exist is not valid text".to_string())
+                })?;
+                Some(Self::conditional_criteria(query)?)
+            }
+            None => None,
+        };
+
+        let result = match criteria {
+            Some(criteria) => controller.patient_service.conditional_create(payload, &criteria, &auth).await,
+            None => controller.patient_service.create_patient(payload, &auth).await.map(|patient| ConditionalCreate::Created(Box::new(patient))),
+        };
+        match result {
+            Ok(ConditionalCreate::Created(patient)) => {
                 tracing::info!("Patient created successfully: {}", patient.id);
-                Ok((StatusCode::CREATED, Json(Self::patient_to_response(patient))))
+                Ok(Self::created_or_existing(StatusCode::CREATED, *patient))
+            }
+            Ok(ConditionalCreate::Existing(id)) => {
+                tracing::info!("Patient already exists, not created: {}", id);
+                // Returning the match is a read of it
+                controller.authorize(&auth, &headers, Action::Read, Resource::Patient(id)).await?;
+                match controller.patient_service.read_patient(id, &auth).await {
+                    Ok(Some(patient)) => Ok(Self::created_or_existing(StatusCode::OK, patient)),
+                    Ok(None) => Err((
+                        StatusCode::NOT_FOUND,
+                        Json(ErrorResponse {
+                            error: "Not found".to_string(),
+                            message: format!("Patient with ID {} not found", id),
+                        }),
+                    )),
+                    Err(e) => {
+                        tracing::error!("Failed to read matched patient: {}", e);
+                        Err((
+                            Self::error_status(&e),
+                            Json(ErrorResponse {
+                                error: "Failed to create patient".to_string(),
+                                message: e.to_string(),
+                            }),
+                        ))
+                    }
+                }
             }
             Err(e) => {
                 tracing::error!("Failed to create patient: {}", e);
                 Err((
-                    StatusCode::BAD_REQUEST,
+                    Self::error_status(&e),
                     Json(ErrorResponse {
                         error: "Failed to create patient".to_string(),
                         message: e.to_string(),
@@ -300,38 +383,186 @@ impl PatientController {
         }
     }
 
+    /// Conditional update (`PUT /?identifier=system|value`): update the
+    /// patient matching the criteria, or create it (201) if none does.
+    /// `If-Match` is optional here; without it the matched version is updated.
+    pub async fn conditional_update_patient(
+        State(controller): State<Arc<PatientController>>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Query(params): Query<Vec<(String, String)>>,
+        Json(payload): Json<PatientCreateRequest>,
+    ) -> Result<(StatusCode, [(HeaderName, String); 2], Json<PatientResponse>), (StatusCode, Json<ErrorResponse>)> {
+        let criteria = Self::criteria_from_params(&params)?;
+        tracing::info!("Conditionally updating patient matching {:?}", criteria);
+        
+        let authorized = controller.conditional_match(&auth, &headers, &criteria, Action::Write).await?;
+        let expected_version = if_match_version(&headers);
+        match controller
+            .patient_service
+            .conditional_update(&criteria, authorized, payload, expected_version.as_deref(), &auth)
+            .await
+        {
+            Ok(ConditionalUpdate::Created(patient)) => {
+                tracing::info!("Patient created by conditional update: {}", patient.id);
+                Ok(Self::created_or_existing(StatusCode::CREATED, patient))
+            }
+            Ok(ConditionalUpdate::Updated(patient)) => {
+                tracing::info!("Patient updated conditionally: {}", patient.id);
+                Ok(Self::created_or_existing(StatusCode::OK, patient))
+            }
+            Err(e) => {
+                tracing::error!("Failed to conditionally update patient: {}", e);
+                Err((
+                    Self::error_status(&e),
+                    Json(ErrorResponse {
+                        error: "Failed to update patient".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// Conditional delete (`DELETE /?identifier=system|value`). Deleting
+    /// when nothing matches succeeds, so retries are safe.
+    pub async fn conditional_delete_patient(
+        State(controller): State<Arc<PatientController>>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let criteria = Self::criteria_from_params(&params)?;
+        tracing::info!("Conditionally deleting patient matching {:?}", criteria);
+        
+        let authorized = controller.conditional_match(&auth, &headers, &criteria, Action::Delete).await?;
+        match controller.patient_service.conditional_delete(&criteria, authorized, &auth).await {
+            Ok(Some(id)) => {
+                tracing::info!("Patient deleted conditionally: {}", id);
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(None) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => {
+                tracing::error!("Failed to conditionally delete patient: {}", e);
+                Err((
+                    Self::error_status(&e),
+                    Json(ErrorResponse {
+                        error: "Failed to delete patient".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// The patient a conditional update or delete matches, once the user
+    /// is allowed `action` on it
+    async fn conditional_match(
+        &self,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        criteria: &[TokenParam],
+        action: Action,
+    ) -> Result<Option<Uuid>, (StatusCode, Json<ErrorResponse>)> {
+        let matched = self.patient_service.conditional_match(criteria).await.map_err(|e| {
+            tracing::error!("Failed to match patients: {}", e);
+            (
+                Self::error_status(&e),
+                Json(ErrorResponse {
+                    error: "Failed to match patients".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+        })?;
+        if let Some(id) = matched {
+            self.authorize(auth, headers, action, Resource::Patient(id)).await?;
+        }
+        Ok(matched)
+    }
+
+    /// Parse conditional criteria from a query string such as
+    /// `identifier=system|value`
+    fn conditional_criteria(query: &str) -> Result<Vec<TokenParam>, (StatusCode, Json<ErrorResponse>)> {
+        let params = parse_search_query(query)
+            .map_err(|e| Self::bad_request("Invalid conditional criteria", e.to_string()))?;
+        Self::criteria_from_params(&params)
+    }
+
+    /// Conditional operations match on `identifier` only. Other parameters
+    /// are rejected rather than ignored, since ignoring one would widen the
+    /// match.
+    fn criteria_from_params(params: &[(String, String)]) -> Result<Vec<TokenParam>, (StatusCode, Json<ErrorResponse>)> {
+        let mut criteria = Vec::new();
+        for (name, value) in params {
+            if name != "identifier" {
+                return Err(Self::bad_request(
+                    "Unsupported conditional criteria",
+                    format!("Conditional operations on Patient support identifier only, not {}", name),
+                ));
+            }
+            criteria.push(
+                TokenParam::parse(value)
+                    .map_err(|e| Self::bad_request("Invalid conditional criteria", e.to_string()))?,
+            );
+        }
+        if criteria.is_empty() {
+            return Err(Self::bad_request(
+                "Missing conditional criteria",
+                "Conditional operations need at least one identifier".to_string(),
+            ));
+        }
+        Ok(criteria)
+    }
+
+    /// Response for a created or matched patient, with its `Location` and `ETag`
+    fn created_or_existing(
+        status: StatusCode,
+        patient: Patient,
+    ) -> (StatusCode, [(HeaderName, String); 2], Json<PatientResponse>) {
+        let headers = [
+            (
+                header::LOCATION,
+                format!("/api/v1/patients/{}/_history/{}", patient.id, version_of(&patient.meta)),
+            ),
+            (header::ETAG, etag(version_of(&patient.meta))),
+        ];
+        (status, headers, Json(Self::patient_to_response(patient)))
+    }
+
+    /// 412/409 for versioning and multiple-match errors, 400 otherwise
+    fn error_status(e: &anyhow::Error) -> StatusCode {
+        e.downcast_ref::<HimsError>()
+            .and_then(precondition_status)
+            .unwrap_or(StatusCode::BAD_REQUEST)
+    }
+
+    fn bad_request(error: &str, message: String) -> (StatusCode, Json<ErrorResponse>) {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                message,
+            }),
+        )
+    }
+
     /// Get patient by ID with authorization
     pub async fn get_patient(
         State(controller): State<Arc<PatientController>>,
+        auth: AuthContext,
         headers: HeaderMap,
         Path(id): Path<Uuid>,
-    ) -> Result<Json<PatientResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<Versioned<PatientResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Retrieving patient: {}", id);
         
-        // For now, skip authorization check until we have full integration
-        // TODO: Re-enable authorization once PatientModule is updated
-        /*
-        let auth_response = controller.authorization_engine.check(
-            AuthorizationRequest {
-                subject: Subject::User(extract_user_from_headers(&headers)?),
-                action: Action::Read,
-                resource: Resource::Patient(id),
-                context: get_user_session_context(extract_user_from_headers(&headers)?, &headers).await?,
-                request_id: Some(uuid::Uuid::new_v4().to_string()),
-            }
-        ).await?;
-        
-        // Handle authorization decision
-        Self::handle_authorization_response(&auth_response)?;
-        */
+        controller.authorize(&auth, &headers, Action::Read, Resource::Patient(id)).await?;
         
-        // If authorized, proceed with the request
-        match controller.patient_service.get_patient(id).await {
+        match controller.patient_service.read_patient(id, &auth).await {
             Ok(Some(patient)) => {
                 tracing::info!("Patient retrieved successfully: {}", id);
                 
                 let response = Self::patient_to_response(patient);
-                Ok(Json(response))
+                Ok(versioned(&response.meta.clone(), response))
             }
             Ok(None) => {
                 tracing::warn!("Patient not found: {}", id);
@@ -356,17 +587,107 @@ impl PatientController {
         }
     }
 
-    /// Search patients with FHIR query parameters
+    /// Read a scanned ABHA card QR code into a patient to register. Posting
+    /// the patient back with the returned `If-None-Exist` registers them
+    /// once, however many times the card is scanned.
+    pub async fn scan_abha_card(
+        Json(payload): Json<AbhaScanRequest>,
+    ) -> Result<Json<AbhaScanResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let card = AbhaCard::from_qr_payload(&payload.payload)
+            .map_err(|e| Self::bad_request("Invalid ABHA QR code", e.to_string()))?;
+        tracing::info!("Scanned ABHA card for registration");
+
+        let patient = PatientCreateRequest {
+            identifier: card.identifiers(),
+            name: card.human_name().into_iter().collect(),
+            telecom: card.telecom().into_iter().collect(),
+            gender: card.gender.clone().unwrap_or(Gender::Unknown),
+            birth_date: card.date_of_birth,
+            address: card.postal_address().into_iter().collect(),
+            marital_status: None,
+            contact: Vec::new(),
+            communication: Vec::new(),
+        };
+        let if_none_exist = format!("identifier={}|{}", ABHA_NUMBER_SYSTEM, card.abha_number);
+        Ok(Json(AbhaScanResponse { card, patient, if_none_exist }))
+    }
+
+    /// A patient's ABHA card QR code, as an SVG image
+    pub async fn abha_qr_code(
+        State(controller): State<Arc<PatientController>>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<([(HeaderName, &'static str); 1], String), (StatusCode, Json<ErrorResponse>)> {
+        controller.authorize(&auth, &headers, Action::Read, Resource::Patient(id)).await?;
+        let patient = match controller.patient_service.read_patient(id, &auth).await {
+            Ok(Some(patient)) => patient,
+            Ok(None) => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Patient not found".to_string(),
+                        message: format!("Patient with id {} not found", id),
+                    }),
+                ))
+            }
+            Err(e) => {
+                tracing::error!("Failed to retrieve patient {}: {}", id, e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Internal server error".to_string(),
+                        message: e.to_string(),
+                    }),
+                ));
+            }
+        };
+
+        let card = AbhaCard::from_patient(&patient).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "ABHA number not found".to_string(),
+                    message: format!("Patient {} has no ABHA number", id),
+                }),
+            )
+        })?;
+        match card.to_qr_svg() {
+            Ok(svg) => Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg)),
+            Err(e) => {
+                tracing::error!("Failed to generate ABHA QR code for patient {}: {}", id, e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to generate ABHA QR code".to_string(),
+                        message: e.to_string(),
+                    }),
+                ))
+            }
+        }
+    }
+
+    /// Search patients with FHIR query parameters. Patients the user may
+    /// not read, and resources referring to them, are left out, so a page
+    /// can be short; follow `next` until there is none.
     pub async fn search_patients(
         State(controller): State<Arc<PatientController>>,
-        Query(params): Query<PatientQuery>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
     ) -> Result<Json<PatientBundle>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Searching patients with params: {:?}", params);
-        
-        match controller.patient_service.search_patients(params).await {
-            Ok(patients) => {
-                tracing::info!("Found {} patients", patients.len());
-                Ok(Json(Self::patients_to_bundle(patients)))
+
+        let search = PatientSearch::from_params(&params)
+            .map_err(|e| Self::bad_request("Invalid search parameters", e.to_string()))?;
+
+        match controller.patient_service.search_patients(&search).await {
+            Ok(result) => {
+                let result = controller.readable(&auth, &headers, result).await?;
+                tracing::info!("Found {} patients", result.patients.len());
+                let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+                Ok(Json(Self::patients_to_bundle(result, link)))
             }
             Err(e) => {
                 tracing::error!("Failed to search patients: {}", e);
@@ -381,23 +702,37 @@ impl PatientController {
         }
     }
 
-    /// Update patient
+    /// Update patient; `If-Match` must name the version being replaced
     pub async fn update_patient(
         State(controller): State<Arc<PatientController>>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Path(id): Path<Uuid>,
         Json(payload): Json<PatientCreateRequest>,
-    ) -> Result<Json<PatientResponse>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<Versioned<PatientResponse>, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Updating patient: {}", id);
         
-        match controller.patient_service.update_patient(id, payload).await {
+        let expected_version = if_match_version(&headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the patient's ETag in If-Match to update it".to_string(),
+                }),
+            )
+        })?;
+        controller.authorize(&auth, &headers, Action::Write, Resource::Patient(id)).await?;
+        
+        match controller.patient_service.update_patient(id, payload, &expected_version, &auth).await {
             Ok(patient) => {
                 tracing::info!("Patient updated successfully: {}", id);
-                Ok(Json(Self::patient_to_response(patient)))
+                let response = Self::patient_to_response(patient);
+                Ok(versioned(&response.meta.clone(), response))
             }
             Err(e) => {
                 tracing::error!("Failed to update patient {}: {}", id, e);
                 Err((
-                    StatusCode::BAD_REQUEST,
+                    Self::error_status(&e),
                     Json(ErrorResponse {
                         error: "Failed to update patient".to_string(),
                         message: e.to_string(),
@@ -410,11 +745,14 @@ impl PatientController {
     /// Delete patient (soft delete)
     pub async fn delete_patient(
         State(controller): State<Arc<PatientController>>,
+        auth: AuthContext,
+        headers: HeaderMap,
         Path(id): Path<Uuid>,
     ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("Deleting patient: {}", id);
         
-        match controller.patient_service.delete_patient(id).await {
+        controller.authorize(&auth, &headers, Action::Delete, Resource::Patient(id)).await?;
+        match controller.patient_service.delete_patient(id, &auth).await {
             Ok(true) => {
                 tracing::info!("Patient deleted successfully: {}", id);
                 Ok(StatusCode::NO_CONTENT)
@@ -443,62 +781,44 @@ impl PatientController {
     }
 
     /// Convert Patient model to FHIR response format
-    fn patient_to_response(patient: Patient) -> PatientResponse {
+    pub(crate) fn patient_to_response(patient: Patient) -> PatientResponse {
         PatientResponse {
-            resourceType: "Patient".to_string(),
+            resource_type: "Patient".to_string(),
             id: patient.id,
             meta: patient.meta,
+            identifier: patient.identifier,
             active: patient.active,
             name: patient.name,
             telecom: patient.telecom,
             gender: patient.gender,
-            birthDate: patient.birth_date,
+            birth_date: patient.birth_date,
             address: patient.address,
-            maritalStatus: patient.marital_status,
+            marital_status: patient.marital_status,
             contact: patient.contact,
             communication: patient.communication,
         }
     }
 
-    /// Redact sensitive patient data based on authorization restrictions
-    fn redact_sensitive_patient_data(mut response: PatientResponse) -> PatientResponse {
-        // Clear sensitive contact information
-        response.telecom = vec![];
-        response.address = vec![];
-        
-        // Redact full names, keep only first name
-        response.name = response.name.into_iter().map(|mut name| {
-            if let Some(ref family) = name.family {
-                if !family.is_empty() {
-                    name.family = Some("[REDACTED]".to_string());
-                }
-            }
-            if name.given.len() > 1 {
-                name.given = vec![name.given[0].clone()];
-            }
-            name
-        }).collect();
-        
-        // Clear contact information
-        response.contact = vec![];
-        
-        tracing::info!("Applied data redaction for patient: {}", response.id);
-        response
-    }
-
-    /// Convert multiple patients to FHIR Bundle format
-    fn patients_to_bundle(patients: Vec<Patient>) -> PatientBundle {
-        let total = patients.len() as u32;
-        let entries: Vec<PatientBundleEntry> = patients
-            .into_iter()
-            .map(|patient| PatientBundleEntry {
-                fullUrl: format!("Patient/{}", patient.id),
-                resource: Self::patient_to_response(patient),
-            })
-            .collect();
+    /// Convert a page of search results to a FHIR searchset Bundle
+    fn patients_to_bundle(result: PatientSearchResult, link: Vec<BundleLink>) -> PatientBundle {
+        let matches = result.patients.into_iter().map(|patient| PatientBundleEntry {
+            full_url: format!("Patient/{}", patient.id),
+            resource: serde_json::to_value(Self::patient_to_response(patient)).unwrap_or_default(),
+            search: EntrySearch {
+                mode: SearchEntryMode::Match,
+            },
+        });
+        let included = result.included.into_iter().map(|included| PatientBundleEntry {
+            full_url: format!("{}/{}", included.resource_type, included.id),
+            resource: included.body(),
+            search: EntrySearch {
+                mode: SearchEntryMode::Include,
+            },
+        });
+        let entries: Vec<PatientBundleEntry> = matches.chain(included).collect();
 
         PatientBundle {
-            resourceType: "Bundle".to_string(),
+            resource_type: "Bundle".to_string(),
             id: Uuid::new_v4(),
             meta: ResourceMeta {
                 version_id: Some("1".to_string()),
@@ -507,7 +827,9 @@ impl PatientController {
                 security: vec![],
                 tag: vec![],
             },
-            total,
+            bundle_type: "searchset".to_string(),
+            total: result.total,
+            link,
             entry: entries,
         }
     }
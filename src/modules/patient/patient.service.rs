@@ -1,24 +1,344 @@
-use sqlx::{PgPool, Row};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
 use uuid::Uuid;
 use anyhow::{Context, Result};
 use chrono::Utc;
 
-use crate::models::{Patient, AuditLog, AuditEventType, AuditAction, AuditOutcome};
+use crate::core::HimsError;
+use crate::models::{Patient, AuditLog, AuditEventType, AuditAction};
+use crate::modules::auth::AuthContext;
+use crate::modules::events::{DomainEvent, EventBus};
 use crate::modules::patient::patient_controller::PatientCreateRequest;
+use crate::standards::fhir::PackValidator;
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{
+    escape_like, parse_sort, split_modifier, DateParam, IncludeParam, IncludedResource, SearchParamDefinition,
+    StringModifier, StringParam, TokenParam, TotalMode,
+};
+use crate::utils::pagination::{count_matches, key_column, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
 
 // Import SQL queries from separate file
 use crate::modules::patient::patient_sql::*;
 
+/// Outcome of a conditional create
+#[derive(Debug)]
+pub enum ConditionalCreate {
+    Created(Box<Patient>),
+    /// A patient already matched the criteria; nothing was created. Only
+    /// its ID is returned, since the caller may not be allowed to read it.
+    Existing(Uuid),
+}
+
+/// Outcome of a conditional update
+#[derive(Debug)]
+pub enum ConditionalUpdate {
+    /// No patient matched, so one was created
+    Created(Patient),
+    Updated(Patient),
+}
+
+/// Page size of a patient search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 20;
+
+/// Largest page a patient search returns
+pub const MAX_SEARCH_COUNT: i64 = 100;
+
+/// Paging of patient searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Orderings `_sort` accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatientSort {
+    Id,
+    LastUpdated,
+    Name,
+    Birthdate,
+    Gender,
+}
+
+impl PatientSort {
+    pub fn from_param(name: &str) -> Option<Self> {
+        match name {
+            "_id" => Some(PatientSort::Id),
+            "_lastUpdated" => Some(PatientSort::LastUpdated),
+            "name" | "family" => Some(PatientSort::Name),
+            "birthdate" => Some(PatientSort::Birthdate),
+            "gender" => Some(PatientSort::Gender),
+            _ => None,
+        }
+    }
+
+    fn key(&self, descending: bool) -> SortKey {
+        match self {
+            PatientSort::Id => SortKey::new("id", "uuid", descending),
+            PatientSort::LastUpdated => SortKey::new("(meta->>'last_updated')", "text", descending),
+            PatientSort::Name => SortKey::new("lower(name->0->>'family')", "text", descending),
+            PatientSort::Birthdate => SortKey::new("birth_date", "date", descending),
+            PatientSort::Gender => SortKey::new("gender", "text", descending),
+        }
+    }
+}
+
+/// Parsed FHIR Patient search. Repeated parameters must all match; the
+/// comma-separated values of one parameter are alternatives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatientSearch {
+    pub ids: Vec<Uuid>,
+    pub active: Option<bool>,
+    pub name: Vec<StringParam>,
+    pub identifier: Vec<TokenParam>,
+    pub birthdate: Vec<DateParam>,
+    /// Any of these genders
+    pub gender: Vec<String>,
+    pub address_city: Vec<StringParam>,
+    /// Sort keys with whether each is descending; ties break on id
+    pub sort: Vec<(PatientSort, bool)>,
+    pub revinclude: Vec<PatientRevInclude>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+/// Resources `_revinclude` can add to a patient search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatientRevInclude {
+    /// `Appointment:patient`
+    Appointment,
+    /// `DocumentReference:patient` or `DocumentReference:subject`
+    DocumentReference,
+}
+
+impl PatientRevInclude {
+    pub fn from_param(include: &IncludeParam) -> Option<Self> {
+        if include.target.as_deref().is_some_and(|target| target != "Patient") {
+            return None;
+        }
+        match (include.source.as_str(), include.param.as_str()) {
+            ("Appointment", "patient" | "actor") => Some(PatientRevInclude::Appointment),
+            ("DocumentReference", "patient" | "subject") => Some(PatientRevInclude::DocumentReference),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PatientSearch {
+    fn default() -> Self {
+        Self {
+            ids: Vec::new(),
+            active: None,
+            name: Vec::new(),
+            identifier: Vec::new(),
+            birthdate: Vec::new(),
+            gender: Vec::new(),
+            address_city: Vec::new(),
+            sort: Vec::new(),
+            revinclude: Vec::new(),
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// A page of patient search results
+#[derive(Debug)]
+pub struct PatientSearchResult {
+    pub patients: Vec<Patient>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    /// Resources referring to the patients on this page, from `_revinclude`
+    pub included: Vec<IncludedResource>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl PatientSearch {
+    /// Search parameters `from_params` supports
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "_id",
+            param_type: "token",
+            documentation: "Logical ID; comma-separated IDs match any",
+        },
+        SearchParamDefinition {
+            name: "active",
+            param_type: "token",
+            documentation: "Whether the patient record is active",
+        },
+        SearchParamDefinition {
+            name: "name",
+            param_type: "string",
+            documentation: "Any part of any name; supports :contains and :exact",
+        },
+        SearchParamDefinition {
+            name: "identifier",
+            param_type: "token",
+            documentation: "Business identifier as system|value",
+        },
+        SearchParamDefinition {
+            name: "birthdate",
+            param_type: "date",
+            documentation: "Date of birth; supports eq, ne, gt, lt, ge and le prefixes",
+        },
+        SearchParamDefinition {
+            name: "gender",
+            param_type: "token",
+            documentation: "Administrative gender; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "address-city",
+            param_type: "string",
+            documentation: "City of any address; supports :contains and :exact",
+        },
+    ];
+
+    /// `_revinclude` values `from_params` supports
+    pub const REVINCLUDES: &'static [&'static str] = &[
+        "Appointment:patient",
+        "Appointment:actor",
+        "DocumentReference:patient",
+        "DocumentReference:subject",
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored, as FHIR
+    /// servers may; invalid values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+
+        for (param, value) in params {
+            let (name, modifier) = split_modifier(param);
+            match name {
+                "_id" => {
+                    for id in value.split(',') {
+                        search.ids.push(Uuid::parse_str(id).map_err(|_| invalid(name, id))?);
+                    }
+                }
+                "active" => {
+                    search.active = Some(value.parse().map_err(|_| invalid(name, value))?);
+                }
+                "name" => search.name.push(StringParam::parse(modifier, value)?),
+                "identifier" => search.identifier.push(TokenParam::parse(value)?),
+                "birthdate" => search.birthdate.push(DateParam::parse(value)?),
+                "gender" => {
+                    for gender in value.split(',') {
+                        if !matches!(gender, "male" | "female" | "other" | "unknown") {
+                            return Err(invalid(name, gender));
+                        }
+                        search.gender.push(gender.to_string());
+                    }
+                }
+                "address-city" => search.address_city.push(StringParam::parse(modifier, value)?),
+                "_sort" => {
+                    for (key, descending) in parse_sort(value) {
+                        let sort = PatientSort::from_param(&key).ok_or_else(|| invalid(name, &key))?;
+                        search.sort.push((sort, descending));
+                    }
+                }
+                "_revinclude" => {
+                    let include = IncludeParam::parse(value)?;
+                    let revinclude = PatientRevInclude::from_param(&include).ok_or_else(|| invalid(name, value))?;
+                    if !search.revinclude.contains(&revinclude) {
+                        search.revinclude.push(revinclude);
+                    }
+                }
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported patient search parameter {}", param),
+            }
+        }
+        search.page.check_cursor(&search.sort_keys())?;
+        Ok(search)
+    }
+
+    /// Orderings of the search, most significant first; ties break on id
+    pub fn sort_keys(&self) -> Vec<SortKey> {
+        self.sort.iter().map(|(sort, descending)| sort.key(*descending)).collect()
+    }
+
+    /// Append the WHERE clause for these criteria. Columns are unqualified,
+    /// so this also serves as a subquery over patients in chained searches.
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        query.push(" WHERE deleted_at IS NULL");
+        if !self.ids.is_empty() {
+            query.push(" AND id = ANY(").push_bind(self.ids.clone()).push(")");
+        }
+        if let Some(active) = self.active {
+            query.push(" AND active = ").push_bind(active);
+        }
+        for name in &self.name {
+            Self::push_string(query, "patient_name_parts(name)", "patient_name_search(name)", name);
+        }
+        if !self.identifier.is_empty() {
+            let (contains, without_system) = identifier_match(&self.identifier);
+            query
+                .push(" AND identifier @> ")
+                .push_bind(contains)
+                .push("::jsonb AND NOT EXISTS (SELECT 1 FROM unnest(")
+                .push_bind(without_system)
+                .push(
+                    "::text[]) AS wanted(value) WHERE NOT EXISTS (\
+                     SELECT 1 FROM jsonb_array_elements(patients.identifier) AS e \
+                     WHERE e->>'value' = wanted.value AND COALESCE(e->>'system', '') = ''))",
+                );
+        }
+        for date in &self.birthdate {
+            date.push_condition(query, "birth_date");
+        }
+        if !self.gender.is_empty() {
+            query.push(" AND gender = ANY(").push_bind(self.gender.clone()).push(")");
+        }
+        for city in &self.address_city {
+            Self::push_string(query, "patient_address_cities(address)", "patient_city_search(address)", city);
+        }
+    }
+
+    /// Append a string match against the indexed expressions of the
+    /// migration: `parts` for `:exact`, `search` (lowercased parts, each
+    /// after a space) otherwise
+    fn push_string(query: &mut QueryBuilder<'_, Postgres>, parts: &str, search: &str, param: &StringParam) {
+        match param.modifier {
+            StringModifier::Exact => {
+                query
+                    .push(format!(" AND {} @> ARRAY[", parts))
+                    .push_bind(param.value.clone())
+                    .push("]::text[]");
+            }
+            StringModifier::StartsWith => {
+                query
+                    .push(format!(" AND {} LIKE ", search))
+                    .push_bind(format!("% {}%", escape_like(&param.value.to_lowercase())));
+            }
+            StringModifier::Contains => {
+                query
+                    .push(format!(" AND {} LIKE ", search))
+                    .push_bind(format!("%{}%", escape_like(&param.value.to_lowercase())));
+            }
+        }
+    }
+}
+
 /// Patient service for healthcare business logic
 #[derive(Debug, Clone)]
 pub struct PatientService {
     pool: PgPool,
+    events: EventBus,
+    /// Profile pack patients must conform to, if the country has one
+    profiles: Option<PackValidator>,
 }
 
 impl PatientService {
     /// Create new patient service
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self::with_events(pool, EventBus::default())
+    }
+
+    /// Create the service publishing to a shared event bus
+    pub fn with_events(pool: PgPool, events: EventBus) -> Self {
+        Self { pool, events, profiles: None }
+    }
+
+    /// Validate patients against a profile pack before they are stored
+    pub fn with_profiles(mut self, profiles: Option<PackValidator>) -> Self {
+        self.profiles = profiles;
+        self
     }
 
     /// Get the database pool (for dependency injection)
@@ -27,22 +347,50 @@ impl PatientService {
     }
 
     /// Create a new patient with FHIR compliance
-    pub async fn create_patient(&self, request: PatientCreateRequest) -> Result<Patient> {
+    pub async fn create_patient(&self, request: PatientCreateRequest, auth: &AuthContext) -> Result<Patient> {
+        // Begin transaction for data consistency
+        let mut tx = self.pool.begin().await
+            .context("Failed to begin transaction")?;
+
+        let patient = self.insert_patient(&mut tx, request, auth).await?;
+
+        // Commit transaction
+        tx.commit().await
+            .context("Failed to commit patient creation")?;
+        self.events.publish(DomainEvent::PatientCreated { patient_id: patient.id });
+
+        tracing::info!("Patient created successfully: {}", patient.id);
+        Ok(patient)
+    }
+
+    /// Insert a patient and its audit log within a transaction
+    /// Reject a patient that does not conform to the profile pack
+    fn conform(&self, patient: &Patient) -> Result<(), HimsError> {
+        match &self.profiles {
+            Some(profiles) => profiles.require(&patient.to_fhir()),
+            None => Ok(()),
+        }
+    }
+
+    async fn insert_patient(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        request: PatientCreateRequest,
+        auth: &AuthContext,
+    ) -> Result<Patient> {
         // Create patient with FHIR metadata
-        let patient = Patient::new(
+        let mut patient = Patient::new(
             request.name,
             request.telecom,
             request.gender,
             request.birth_date,
         );
-
-        // Begin transaction for data consistency
-        let mut tx = self.pool.begin().await
-            .context("Failed to begin transaction")?;
+        patient.identifier = request.identifier;
+        self.conform(&patient)?;
 
         // Insert patient into database using simple query
         let _result = sqlx::query(INSERT_PATIENT)
-            .bind(&patient.id)
+            .bind(patient.id)
             .bind(patient.active)
             .bind(serde_json::to_value(&patient.name)?)
             .bind(serde_json::to_value(&patient.telecom)?)
@@ -53,30 +401,44 @@ impl PatientService {
             .bind(serde_json::to_value(&patient.contact)?)
             .bind(serde_json::to_value(&patient.communication)?)
             .bind(serde_json::to_value(&patient.meta)?)
-            .execute(&mut *tx)
+            .bind(serde_json::to_value(&patient.identifier)?)
+            .execute(&mut **tx)
             .await
             .context("Failed to insert patient")?;
 
         // Create audit log with correct event type
-        let audit_log = AuditLog::new(
+        let audit_log = auth.audit(AuditLog::new(
             AuditEventType::Create,
             AuditAction::Create,
             "Patient".to_string(),
-        )
-        .with_user(Uuid::new_v4()) // TODO: Get from authentication context
+        ))
+        .with_patient(patient.id)
         .with_resource(patient.id);
 
-        self.create_audit_log(&mut tx, &audit_log).await?;
+        self.create_audit_log(tx, &audit_log).await?;
 
-        // Commit transaction
-        tx.commit().await
-            .context("Failed to commit patient creation")?;
+        Ok(patient)
+    }
 
-        tracing::info!("Patient created successfully: {}", patient.id);
+    /// Read a patient on behalf of a user, logging the access
+    pub async fn read_patient(&self, id: Uuid, auth: &AuthContext) -> Result<Option<Patient>> {
+        let patient = self.get_patient(id).await?;
+        if patient.is_some() {
+            let audit_log = auth.audit(AuditLog::new(
+                AuditEventType::Access,
+                AuditAction::Read,
+                "Patient".to_string(),
+            ))
+            .with_patient(id)
+            .with_resource(id);
+            self.create_audit_log_async(&audit_log).await?;
+        }
         Ok(patient)
     }
 
-    /// Get patient by ID with audit logging
+    /// Get patient by ID. Not audited; services looking patients up for
+    /// their own resources audit those, and requests read through
+    /// `read_patient`.
     pub async fn get_patient(&self, id: Uuid) -> Result<Option<Patient>> {
         // Use simple query instead of macro
         let result = sqlx::query(GET_PATIENT_BY_ID)
@@ -89,6 +451,8 @@ impl PatientService {
             Some(row) => {
                 let patient = Patient {
                     id: row.try_get("id")?,
+                    identifier: serde_json::from_value(row.try_get("identifier")?)
+                        .context("Failed to deserialize patient identifier")?,
                     active: row.try_get("active")?,
                     name: serde_json::from_value(row.try_get("name")?)
                         .context("Failed to deserialize patient name")?,
@@ -116,18 +480,6 @@ impl PatientService {
                         .context("Failed to deserialize patient meta")?,
                 };
 
-                // Log patient access with correct event type
-                let audit_log = AuditLog::new(
-                    AuditEventType::Access,
-                    AuditAction::Read,
-                    "Patient".to_string(),
-                )
-                .with_user(Uuid::new_v4()) // TODO: Get from authentication context
-                .with_patient(id)
-                .with_resource(id);
-
-                self.create_audit_log_async(&audit_log).await?;
-
                 tracing::info!("Patient retrieved: {}", id);
                 Ok(Some(patient))
             }
@@ -138,24 +490,28 @@ impl PatientService {
         }
     }
 
-    /// Search patients with filters
-    pub async fn search_patients(&self, query: crate::modules::patient::patient_controller::PatientQuery) -> Result<Vec<Patient>> {
-        let limit = query._count.unwrap_or(20).min(100) as i64;
-        let offset = query._offset.unwrap_or(0) as i64;
+    /// Search patients with FHIR search parameters
+    pub async fn search_patients(&self, search: &PatientSearch) -> Result<PatientSearchResult> {
+        let keys = search.sort_keys();
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_PATIENTS);
+        query.push(format!(", {} AS page_key FROM patients", key_column(&keys)));
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &keys);
+        search.page.push_order(&mut query, &keys);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
 
-        // Use simple query with basic pagination
-        let rows = sqlx::query(SEARCH_PATIENTS)
-            .bind(query.active)
-            .bind(limit)
-            .bind(offset)
+        let rows = query
+            .build()
             .fetch_all(&self.pool)
             .await
             .context("Failed to search patients")?;
 
-        let mut patients = Vec::new();
+        let mut fetched = Vec::new();
         for row in rows {
             let patient = Patient {
                 id: row.try_get("id")?,
+                identifier: serde_json::from_value(row.try_get("identifier")?)
+                    .context("Failed to deserialize patient identifier")?,
                 active: row.try_get("active")?,
                 name: serde_json::from_value(row.try_get("name")?)
                     .context("Failed to deserialize patient name")?,
@@ -182,24 +538,125 @@ impl PatientService {
                 meta: serde_json::from_value(row.try_get("meta")?)
                     .context("Failed to deserialize patient meta")?,
             };
-            patients.push(patient);
+            fetched.push((patient, RowKey::from_column(row.try_get("page_key")?)?));
         }
+        let page = search.page.page(fetched);
+
+        let total = count_matches(&self.pool, search.page.total(), COUNT_PATIENTS, ESTIMATE_PATIENTS, |query| {
+            search.push_filters(query)
+        })
+        .await
+        .context("Failed to count patients")?;
 
-        tracing::info!("Found {} patients", patients.len());
-        Ok(patients)
+        let included = self.revincluded(&search.revinclude, &page.items).await?;
+
+        tracing::info!("Found {} patients", page.items.len());
+        Ok(PatientSearchResult {
+            patients: page.items,
+            total,
+            included,
+            next: page.next,
+            previous: page.previous,
+        })
     }
 
-    /// Update patient
-    pub async fn update_patient(&self, id: Uuid, request: PatientCreateRequest) -> Result<Patient> {
+    /// Resources referring to any of `patients`, fetched in one query
+    async fn revincluded(&self, revinclude: &[PatientRevInclude], patients: &[Patient]) -> Result<Vec<IncludedResource>> {
+        if revinclude.is_empty() || patients.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<Uuid> = patients.iter().map(|patient| patient.id).collect();
+
+        let mut query = QueryBuilder::<Postgres>::new("");
+        for (i, include) in revinclude.iter().enumerate() {
+            if i > 0 {
+                query.push(" UNION ALL ");
+            }
+            query.push(match include {
+                PatientRevInclude::Appointment => REVINCLUDE_APPOINTMENTS,
+                PatientRevInclude::DocumentReference => REVINCLUDE_DOCUMENT_REFERENCES,
+            });
+            query.push_bind(ids.clone()).push(")");
+        }
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch revincluded resources")?;
+        rows.iter()
+            .map(|row| -> Result<IncludedResource> {
+                Ok(IncludedResource {
+                    resource_type: row.try_get("resource_type")?,
+                    id: row.try_get("id")?,
+                    resource: row.try_get("resource")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Update patient if it is still at `expected_version`, bumping its
+    /// version. Fails with `PreconditionFailed` when the patient has moved
+    /// on, or `ConflictError` when a concurrent update wins the race.
+    pub async fn update_patient(
+        &self,
+        id: Uuid,
+        request: PatientCreateRequest,
+        expected_version: &str,
+        auth: &AuthContext,
+    ) -> Result<Patient> {
         let pool = &self.pool;
         
         // Begin transaction
         let mut tx = pool.begin().await
             .context("Failed to begin transaction")?;
 
+        self.update_patient_in(&mut tx, id, request, expected_version, auth).await?;
+
+        // Commit transaction
+        tx.commit().await
+            .context("Failed to commit patient update")?;
+        self.events.publish(DomainEvent::PatientUpdated { patient_id: id });
+
+        // Fetch updated patient
+        self.get_patient(id).await?
+            .ok_or_else(|| anyhow::anyhow!("Patient not found after update"))
+    }
+
+    /// Version-checked update and its audit log within a transaction
+    async fn update_patient_in(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+        request: PatientCreateRequest,
+        expected_version: &str,
+        auth: &AuthContext,
+    ) -> Result<()> {
+        let current_version = Self::current_version(tx, id).await?;
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Patient {} is at version {}, not {}", id, current_version, expected_version),
+            }
+            .into());
+        }
+
+        let mut patient = Patient::new(
+            request.name.clone(),
+            request.telecom.clone(),
+            request.gender.clone(),
+            request.birth_date,
+        );
+        patient.id = id;
+        patient.identifier = request.identifier.clone();
+        patient.address = request.address.clone();
+        patient.marital_status = request.marital_status.clone();
+        patient.contact = request.contact.clone();
+        patient.communication = request.communication.clone();
+        self.conform(&patient)?;
+
         // Update patient
         let updated_at = Utc::now();
-        sqlx::query(UPDATE_PATIENT)
+        let rows_affected = sqlx::query(UPDATE_PATIENT)
             .bind(id)
             .bind(serde_json::to_value(&request.name)?)
             .bind(serde_json::to_value(&request.telecom)?)
@@ -210,32 +667,43 @@ impl PatientService {
             .bind(serde_json::to_value(&request.contact)?)
             .bind(serde_json::to_value(&request.communication)?)
             .bind(serde_json::to_value(updated_at.to_rfc3339())?)
-            .execute(&mut *tx)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .bind(serde_json::to_value(&request.identifier)?)
+            .execute(&mut **tx)
             .await
-            .context("Failed to update patient")?;
+            .context("Failed to update patient")?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Patient {} was modified concurrently", id),
+            }
+            .into());
+        }
 
         // Create audit log
-        let audit_log = AuditLog::new(
+        let audit_log = auth.audit(AuditLog::new(
             AuditEventType::Update,
             AuditAction::Update,
             "Patient".to_string(),
-        )
-        .with_user(Uuid::new_v4()) // TODO: Get from authentication context
+        ))
+        .with_patient(id)
         .with_resource(id);
 
-        self.create_audit_log(&mut tx, &audit_log).await?;
-
-        // Commit transaction
-        tx.commit().await
-            .context("Failed to commit patient update")?;
+        self.create_audit_log(tx, &audit_log).await
+    }
 
-        // Fetch updated patient
-        self.get_patient(id).await?
-            .ok_or_else(|| anyhow::anyhow!("Patient not found after update"))
+    async fn current_version(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, id: Uuid) -> Result<String> {
+        sqlx::query_scalar(GET_PATIENT_VERSION)
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await
+            .context("Failed to fetch patient version")?
+            .ok_or_else(|| anyhow::anyhow!("Patient not found: {}", id))
     }
 
     /// Soft delete patient
-    pub async fn delete_patient(&self, id: Uuid) -> Result<bool> {
+    pub async fn delete_patient(&self, id: Uuid, auth: &AuthContext) -> Result<bool> {
         let pool = &self.pool;
         
         let rows_affected = sqlx::query(DELETE_PATIENT)
@@ -247,15 +715,16 @@ impl PatientService {
 
         if rows_affected > 0 {
             // Create audit log
-            let audit_log = AuditLog::new(
+            let audit_log = auth.audit(AuditLog::new(
                 AuditEventType::Delete,
                 AuditAction::Delete,
                 "Patient".to_string(),
-            )
-            .with_user(Uuid::new_v4()) // TODO: Get from authentication context
+            ))
+            .with_patient(id)
             .with_resource(id);
 
             self.create_audit_log_async(&audit_log).await?;
+            self.events.publish(DomainEvent::PatientDeleted { patient_id: id });
             tracing::info!("Patient soft deleted: {}", id);
             Ok(true)
         } else {
@@ -263,6 +732,188 @@ impl PatientService {
         }
     }
 
+    /// Create the patient unless one matches `criteria` (FHIR
+    /// `If-None-Exist`). Several matches fail with `PreconditionFailed`.
+    pub async fn conditional_create(
+        &self,
+        request: PatientCreateRequest,
+        criteria: &[TokenParam],
+        auth: &AuthContext,
+    ) -> Result<ConditionalCreate> {
+        let mut tx = self.pool.begin().await
+            .context("Failed to begin transaction")?;
+
+        match Self::match_identifiers(&mut tx, criteria).await?.as_slice() {
+            [] => {
+                let patient = self.insert_patient(&mut tx, request, auth).await?;
+                tx.commit().await
+                    .context("Failed to commit patient creation")?;
+                self.events.publish(DomainEvent::PatientCreated { patient_id: patient.id });
+                tracing::info!("Patient created conditionally: {}", patient.id);
+                Ok(ConditionalCreate::Created(Box::new(patient)))
+            }
+            [id] => Ok(ConditionalCreate::Existing(*id)),
+            _ => Err(Self::multiple_matches(criteria)),
+        }
+    }
+
+    /// The patient matching `criteria`, if any, so callers can authorize a
+    /// conditional update or delete before making it. Several matches fail
+    /// with `PreconditionFailed`.
+    pub async fn conditional_match(&self, criteria: &[TokenParam]) -> Result<Option<Uuid>> {
+        let mut tx = self.pool.begin().await
+            .context("Failed to begin transaction")?;
+
+        match Self::match_identifiers(&mut tx, criteria).await?.as_slice() {
+            [] => Ok(None),
+            [id] => Ok(Some(*id)),
+            _ => Err(Self::multiple_matches(criteria)),
+        }
+    }
+
+    /// Update the patient matching `criteria`, or create it if none does.
+    /// The update is checked against `expected_version` when given, else
+    /// against the version matched. Several matches fail with
+    /// `PreconditionFailed`, as does a match other than `authorized`, the
+    /// one `conditional_match` found and the caller checked.
+    pub async fn conditional_update(
+        &self,
+        criteria: &[TokenParam],
+        authorized: Option<Uuid>,
+        request: PatientCreateRequest,
+        expected_version: Option<&str>,
+        auth: &AuthContext,
+    ) -> Result<ConditionalUpdate> {
+        let mut tx = self.pool.begin().await
+            .context("Failed to begin transaction")?;
+
+        let matched = Self::match_identifiers(&mut tx, criteria).await?;
+        Self::check_match(criteria, &matched, authorized)?;
+        match matched.as_slice() {
+            [] => {
+                let patient = self.insert_patient(&mut tx, request, auth).await?;
+                tx.commit().await
+                    .context("Failed to commit patient creation")?;
+                self.events.publish(DomainEvent::PatientCreated { patient_id: patient.id });
+                tracing::info!("Patient created by conditional update: {}", patient.id);
+                Ok(ConditionalUpdate::Created(patient))
+            }
+            [id] => {
+                let id = *id;
+                let version = match expected_version {
+                    Some(version) => version.to_string(),
+                    None => Self::current_version(&mut tx, id).await?,
+                };
+                self.update_patient_in(&mut tx, id, request, &version, auth).await?;
+                tx.commit().await
+                    .context("Failed to commit patient update")?;
+                self.events.publish(DomainEvent::PatientUpdated { patient_id: id });
+                let patient = self.get_patient(id).await?
+                    .ok_or_else(|| anyhow::anyhow!("Patient not found after update"))?;
+                Ok(ConditionalUpdate::Updated(patient))
+            }
+            _ => Err(Self::multiple_matches(criteria)),
+        }
+    }
+
+    /// Soft delete the patient matching `criteria`, returning its ID, or
+    /// `None` if none matches. Several matches fail with `PreconditionFailed`,
+    /// as does a match other than `authorized`.
+    pub async fn conditional_delete(
+        &self,
+        criteria: &[TokenParam],
+        authorized: Option<Uuid>,
+        auth: &AuthContext,
+    ) -> Result<Option<Uuid>> {
+        let mut tx = self.pool.begin().await
+            .context("Failed to begin transaction")?;
+
+        let matched = Self::match_identifiers(&mut tx, criteria).await?;
+        Self::check_match(criteria, &matched, authorized)?;
+        let id = match matched.as_slice() {
+            [] => return Ok(None),
+            [id] => *id,
+            _ => return Err(Self::multiple_matches(criteria)),
+        };
+
+        sqlx::query(DELETE_PATIENT)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete patient")?;
+
+        let audit_log = auth.audit(AuditLog::new(
+            AuditEventType::Delete,
+            AuditAction::Delete,
+            "Patient".to_string(),
+        ))
+        .with_patient(id)
+        .with_resource(id);
+        self.create_audit_log(&mut tx, &audit_log).await?;
+
+        tx.commit().await
+            .context("Failed to commit patient deletion")?;
+        self.events.publish(DomainEvent::PatientDeleted { patient_id: id });
+        tracing::info!("Patient soft deleted conditionally: {}", id);
+        Ok(Some(id))
+    }
+
+    /// Active patients matching identifier criteria (at most two). Locks
+    /// each identifier for the rest of the transaction first, so identical
+    /// conditional requests racing each other cannot both see no match.
+    async fn match_identifiers(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        criteria: &[TokenParam],
+    ) -> Result<Vec<Uuid>> {
+        // Sorted, so concurrent requests take shared locks in the same order
+        let mut keys: Vec<String> = criteria
+            .iter()
+            .map(|token| format!(
+                "Patient.identifier:{}|{}",
+                token.system.as_deref().unwrap_or("*"),
+                token.code.as_deref().unwrap_or("*")
+            ))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        for key in &keys {
+            sqlx::query(LOCK_PATIENT_IDENTIFIER)
+                .bind(key)
+                .execute(&mut **tx)
+                .await
+                .context("Failed to lock patient identifier")?;
+        }
+
+        let (contains, without_system) = identifier_match(criteria);
+        sqlx::query_scalar(FIND_PATIENTS_BY_IDENTIFIER)
+            .bind(contains)
+            .bind(without_system)
+            .fetch_all(&mut **tx)
+            .await
+            .context("Failed to match patient identifiers")
+    }
+
+    /// Fail unless a single match is the one the caller authorized. It can
+    /// differ when patients change between `conditional_match` and the write.
+    fn check_match(criteria: &[TokenParam], matched: &[Uuid], authorized: Option<Uuid>) -> Result<()> {
+        match (matched, authorized) {
+            ([], None) => Ok(()),
+            ([id], Some(authorized)) if *id == authorized => Ok(()),
+            ([_, _, ..], _) => Err(Self::multiple_matches(criteria)),
+            _ => Err(HimsError::PreconditionFailed {
+                message: format!("Patients matching {:?} changed during the request", criteria),
+            }
+            .into()),
+        }
+    }
+
+    fn multiple_matches(criteria: &[TokenParam]) -> anyhow::Error {
+        HimsError::PreconditionFailed {
+            message: format!("Multiple patients match {:?}", criteria),
+        }
+        .into()
+    }
+
     /// Create audit log entry within transaction
     async fn create_audit_log(&self, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, audit_log: &AuditLog) -> Result<()> {
         sqlx::query(INSERT_AUDIT_LOG)
@@ -276,6 +927,10 @@ impl PatientService {
             .bind(audit_log.outcome.to_string())
             .bind(audit_log.timestamp)
             .bind(audit_log.details.as_ref())
+            .bind(audit_log.source_ip.as_ref())
+            .bind(audit_log.user_agent.as_ref())
+            .bind(audit_log.session_id.as_ref())
+            .bind(audit_log.purpose_of_use.as_ref())
             .execute(&mut **tx)
             .await
             .context("Failed to create audit log")?;
@@ -298,6 +953,10 @@ impl PatientService {
             .bind(audit_log.outcome.to_string())
             .bind(audit_log.timestamp)
             .bind(audit_log.details.as_ref())
+            .bind(audit_log.source_ip.as_ref())
+            .bind(audit_log.user_agent.as_ref())
+            .bind(audit_log.session_id.as_ref())
+            .bind(audit_log.purpose_of_use.as_ref())
             .execute(pool)
             .await
             .context("Failed to create audit log")?;
@@ -306,60 +965,151 @@ impl PatientService {
     }
 }
 
-// Add trait implementations for enum serialization
-impl ToString for crate::models::Gender {
-    fn to_string(&self) -> String {
-        match self {
-            crate::models::Gender::Male => "male".to_string(),
-            crate::models::Gender::Female => "female".to_string(),
-            crate::models::Gender::Other => "other".to_string(),
-            crate::models::Gender::Unknown => "unknown".to_string(),
-        }
+impl std::fmt::Display for crate::models::Gender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            crate::models::Gender::Male => "male",
+            crate::models::Gender::Female => "female",
+            crate::models::Gender::Other => "other",
+            crate::models::Gender::Unknown => "unknown",
+        })
     }
 }
 
-impl ToString for AuditEventType {
-    fn to_string(&self) -> String {
-        match self {
-            AuditEventType::Create => "create".to_string(),
-            AuditEventType::Read => "read".to_string(),
-            AuditEventType::Update => "update".to_string(),
-            AuditEventType::Delete => "delete".to_string(),
-            AuditEventType::Access => "access".to_string(),
-            AuditEventType::Export => "export".to_string(),
-            AuditEventType::Authentication => "authentication".to_string(),
-            AuditEventType::SystemAccess => "system-access".to_string(),
-            AuditEventType::PatientAccess => "patient-access".to_string(),
-            AuditEventType::DataModification => "data-modification".to_string(),
+/// Split identifier criteria into a JSON array the patient's identifiers
+/// must contain, and values that must appear without a system
+fn identifier_match(criteria: &[TokenParam]) -> (serde_json::Value, Vec<String>) {
+    let mut contains = Vec::new();
+    let mut without_system = Vec::new();
+    for token in criteria {
+        if token.requires_no_system() {
+            without_system.extend(token.code.clone());
+            continue;
+        }
+        let mut element = serde_json::Map::new();
+        if let Some(system) = &token.system {
+            element.insert("system".to_string(), serde_json::Value::String(system.clone()));
         }
+        if let Some(code) = &token.code {
+            element.insert("value".to_string(), serde_json::Value::String(code.clone()));
+        }
+        contains.push(serde_json::Value::Object(element));
     }
+    (serde_json::Value::Array(contains), without_system)
 }
 
-impl ToString for AuditAction {
-    fn to_string(&self) -> String {
-        match self {
-            AuditAction::Create => "create".to_string(),
-            AuditAction::Read => "read".to_string(),
-            AuditAction::Update => "update".to_string(),
-            AuditAction::Delete => "delete".to_string(),
-            AuditAction::Execute => "execute".to_string(),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_match() {
+        let criteria = vec![
+            TokenParam::parse("http://hospital.org/mrn|123").unwrap(),
+            TokenParam::parse("456").unwrap(),
+            TokenParam::parse("|789").unwrap(),
+        ];
+        let (contains, without_system) = identifier_match(&criteria);
+        assert_eq!(
+            contains,
+            serde_json::json!([{"system": "http://hospital.org/mrn", "value": "123"}, {"value": "456"}])
+        );
+        assert_eq!(without_system, vec!["789".to_string()]);
     }
-}
 
-impl ToString for AuditOutcome {
-    fn to_string(&self) -> String {
-        match self {
-            AuditOutcome::Success => "success".to_string(),
-            AuditOutcome::MinorFailure => "minor-failure".to_string(),
-            AuditOutcome::SeriousFailure => "serious-failure".to_string(),
-            AuditOutcome::MajorFailure => "major-failure".to_string(),
+    #[test]
+    fn test_check_match() {
+        let criteria = vec![TokenParam::parse("http://hospital.org/mrn|123").unwrap()];
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(PatientService::check_match(&criteria, &[], None).is_ok());
+        assert!(PatientService::check_match(&criteria, &[a], Some(a)).is_ok());
+        // The match changed since it was authorized
+        assert!(PatientService::check_match(&criteria, &[b], Some(a)).is_err());
+        assert!(PatientService::check_match(&criteria, &[a], None).is_err());
+        assert!(PatientService::check_match(&criteria, &[], Some(a)).is_err());
+        assert!(PatientService::check_match(&criteria, &[a, b], Some(a)).is_err());
+    }
+
+    #[test]
+    fn test_search_from_params() {
+        let params: Vec<(String, String)> = [
+            ("name:contains", "smi"),
+            ("birthdate", "ge1990"),
+            ("birthdate", "lt2000-06"),
+            ("gender", "female,other"),
+            ("_sort", "-birthdate,name"),
+            ("_total", "none"),
+            ("_count", "500"),
+            ("_format", "json"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        let search = PatientSearch::from_params(&params).unwrap();
+        assert_eq!(search.name[0].modifier, StringModifier::Contains);
+        assert_eq!(search.birthdate.len(), 2);
+        assert_eq!(search.gender, vec!["female".to_string(), "other".to_string()]);
+        assert_eq!(search.sort, vec![(PatientSort::Birthdate, true), (PatientSort::Name, false)]);
+        assert_eq!(search.page.total(), TotalMode::None);
+        assert_eq!(search.page.count, MAX_SEARCH_COUNT);
+
+        let bad = vec![("gender".to_string(), "m".to_string())];
+        assert!(PatientSearch::from_params(&bad).is_err());
+        let bad = vec![("_sort".to_string(), "telecom".to_string())];
+        assert!(PatientSearch::from_params(&bad).is_err());
+        let bad = vec![("_offset".to_string(), "20".to_string())];
+        assert!(PatientSearch::from_params(&bad).is_err());
+
+        let revinclude = vec![
+            ("_revinclude".to_string(), "Appointment:patient".to_string()),
+            ("_revinclude".to_string(), "DocumentReference:subject".to_string()),
+        ];
+        assert_eq!(
+            PatientSearch::from_params(&revinclude).unwrap().revinclude,
+            vec![PatientRevInclude::Appointment, PatientRevInclude::DocumentReference]
+        );
+        let bad = vec![("_revinclude".to_string(), "Encounter:patient".to_string())];
+        assert!(PatientSearch::from_params(&bad).is_err());
+    }
+
+    #[test]
+    fn test_advertised_params_are_supported() {
+        for param in PatientSearch::SEARCH_PARAMS {
+            let value = match param.name {
+                "_id" => Uuid::new_v4().to_string(),
+                "active" => "true".to_string(),
+                "gender" => "female".to_string(),
+                "birthdate" => "1990".to_string(),
+                _ => "x|1".to_string(),
+            };
+            let search = PatientSearch::from_params(&[(param.name.to_string(), value)]).unwrap();
+            assert_ne!(search, PatientSearch::default(), "{} was ignored", param.name);
+        }
+        for include in PatientSearch::REVINCLUDES {
+            let params = [("_revinclude".to_string(), include.to_string())];
+            assert!(PatientSearch::from_params(&params).is_ok(), "{}", include);
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn test_search_sql() {
+        let search = PatientSearch {
+            name: vec![StringParam::parse(None, "Smi").unwrap()],
+            birthdate: vec![DateParam::parse("gt1990").unwrap()],
+            sort: vec![(PatientSort::Birthdate, true)],
+            ..Default::default()
+        };
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM patients");
+        search.push_filters(&mut query);
+        search.page.push_order(&mut query, &search.sort_keys());
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM patients WHERE deleted_at IS NULL AND patient_name_search(name) LIKE $1 \
+             AND birth_date >= $2 ORDER BY birth_date DESC NULLS LAST, id"
+        );
+    }
+
     #[tokio::test]
     async fn test_patient_service_creation() {
         // This would require a test database setup
@@ -1,52 +1,117 @@
-/// SQL queries for patient operations
-/// This file contains all SQL queries used by the patient service
+//! SQL queries for patient operations
+//! This file contains all SQL queries used by the patient service
 
 /// Insert a new patient into the database
 pub const INSERT_PATIENT: &str = r#"
     INSERT INTO patients (
         id, active, name, telecom, gender, birth_date, 
-        address, marital_status, contact, communication, meta
-    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        address, marital_status, contact, communication, meta, identifier
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
 "#;
 
 /// Get patient by ID
 pub const GET_PATIENT_BY_ID: &str = r#"
     SELECT id, active, name, telecom, gender, birth_date,
            address, marital_status, contact, communication, 
-           managing_organization, meta
+           managing_organization, meta, identifier
     FROM patients 
     WHERE id = $1 AND active = true
 "#;
 
-/// Search patients with pagination
+/// Start of a patient search: its columns, which the service follows with
+/// the page key, `FROM patients`, the WHERE clause built from the search
+/// parameters and the page's cursor, order and limit
 pub const SEARCH_PATIENTS: &str = r#"
     SELECT id, active, name, telecom, gender, birth_date,
            address, marital_status, contact, communication, 
-           managing_organization, meta
-    FROM patients 
-    WHERE active = COALESCE($1, active)
-    ORDER BY id
-    LIMIT $2 OFFSET $3
+           managing_organization, meta, identifier
 "#;
 
-/// Update patient information
+/// Start of an accurate patient search total, completed like SEARCH_PATIENTS
+pub const COUNT_PATIENTS: &str = r#"
+    SELECT COUNT(*) FROM patients
+"#;
+
+/// Start of the query plan of a patient search, whose row estimate answers
+/// `_total=estimate` without counting
+pub const ESTIMATE_PATIENTS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM patients
+"#;
+
+/// Appointments of the patients in $1 for `_revinclude`; the service
+/// supplies the bind and the closing parenthesis
+pub const REVINCLUDE_APPOINTMENTS: &str = r#"
+    SELECT 'Appointment' AS resource_type, id, to_jsonb(a) AS resource
+    FROM appointments a
+    WHERE deleted_at IS NULL AND patient_id = ANY("#;
+
+/// Documents of the patients in $1 for `_revinclude`, completed like
+/// REVINCLUDE_APPOINTMENTS
+pub const REVINCLUDE_DOCUMENT_REFERENCES: &str = r#"
+    SELECT 'DocumentReference' AS resource_type, id, to_jsonb(m) AS resource
+    FROM medical_records m
+    WHERE deleted_at IS NULL AND patient_id = ANY("#;
+
+/// Get the current version of an active patient
+pub const GET_PATIENT_VERSION: &str = r#"
+    SELECT COALESCE(meta->>'version_id', '1')
+    FROM patients
+    WHERE id = $1 AND active = true
+"#;
+
+/// Update patient information if it is still at version $12, moving it to
+/// version $11
 pub const UPDATE_PATIENT: &str = r#"
     UPDATE patients 
     SET name = $2, telecom = $3, gender = $4, birth_date = $5,
         address = $6, marital_status = $7, contact = $8, 
-        communication = $9, meta = jsonb_set(meta, '{lastUpdated}', $10)
-    WHERE id = $1
+        communication = $9, identifier = $13,
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', $10), '{version_id}', to_jsonb($11::text))
+    WHERE id = $1 AND active = true AND COALESCE(meta->>'version_id', '1') = $12
+"#;
+
+/// IDs of active patients matching identifier criteria: their identifiers
+/// contain every element of $1, and for each value in $2 there is an
+/// identifier with that value and no system. At most two, as conditional
+/// operations only need to tell one match from several.
+pub const FIND_PATIENTS_BY_IDENTIFIER: &str = r#"
+    SELECT id
+    FROM patients p
+    WHERE active = true
+        AND identifier @> $1::jsonb
+        AND NOT EXISTS (
+            SELECT 1 FROM unnest($2::text[]) AS wanted(value)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM jsonb_array_elements(p.identifier) AS e
+                WHERE e->>'value' = wanted.value AND COALESCE(e->>'system', '') = ''
+            )
+        )
+    ORDER BY id
+    LIMIT 2
+"#;
+
+/// Serialize conditional operations on the same identifier until the
+/// transaction ends
+pub const LOCK_PATIENT_IDENTIFIER: &str = r#"
+    SELECT pg_advisory_xact_lock(hashtext($1))
 "#;
 
-/// Soft delete patient (set active = false)
+/// Soft delete patient (set active = false and stamp deleted_at)
 pub const DELETE_PATIENT: &str = r#"
-    UPDATE patients SET active = false WHERE id = $1 AND active = true
+    UPDATE patients SET active = false, deleted_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND active = true
 "#;
 
-/// Insert audit log entry
+/// Insert audit log entry; IDs are bound as text
 pub const INSERT_AUDIT_LOG: &str = r#"
     INSERT INTO audit_logs (
-        id, event_type, user_id, patient_id, resource_type, 
-        resource_id, action, outcome, timestamp, details
-    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        id, event_type, user_id, patient_id, resource_type,
+        resource_id, action, outcome, timestamp, details,
+        source_ip, user_agent, session_id, purpose_of_use
+    ) VALUES (
+        $1::uuid, $2, NULLIF($3, '')::uuid, $4::uuid, $5, NULLIF($6, '')::uuid, $7, $8, $9, $10,
+        $11::inet, $12, $13, $14
+    )
 "#;
\ No newline at end of file
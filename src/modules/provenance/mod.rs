@@ -0,0 +1,47 @@
+//! Provenance Module
+//!
+//! This module serves the provenance of clinical resource changes including:
+//! - Acting user, on-behalf-of organization, reason and source system of every version
+//! - FHIR Provenance search by `target` resource or `agent` user for medico-legal review
+//! - `X-Source-System` on requests relaying changes from other systems
+
+#[path = "provenance.controller.rs"]
+pub mod provenance_controller;
+#[path = "provenance.service.rs"]
+pub mod provenance_service;
+#[path = "provenance.sql.rs"]
+pub mod provenance_sql;
+
+pub use provenance_controller::ProvenanceController;
+pub use provenance_service::{ProvenanceRecord, ProvenanceSearch, ProvenanceService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Provenance Module Configuration
+pub struct ProvenanceModule {
+    pub service: Arc<ProvenanceService>,
+    pub controller: Arc<ProvenanceController>,
+}
+
+impl ProvenanceModule {
+    /// Create a new Provenance Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(ProvenanceService::new(db_pool));
+        let controller = Arc::new(ProvenanceController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<ProvenanceService> {
+        self.service.clone()
+    }
+}
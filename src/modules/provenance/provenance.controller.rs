@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{BUNDLE_RESOURCE_TYPE, PROVENANCE_RESOURCE_TYPE};
+use crate::modules::provenance::provenance_service::ProvenanceSearch;
+use crate::modules::provenance::ProvenanceService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Serves FHIR Provenance for medico-legal review
+pub struct ProvenanceController {
+    provenance_service: Arc<ProvenanceService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProvenanceQuery {
+    /// `{type}/{id}` of the changed resource
+    pub target: Option<String>,
+    /// `User/{id}` who made the changes
+    pub agent: Option<String>,
+    pub _since: Option<DateTime<Utc>>,
+    pub _count: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl ProvenanceController {
+    /// Create new controller with injected service
+    pub fn new(provenance_service: Arc<ProvenanceService>) -> Self {
+        Self { provenance_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/", Self::search, "Search Provenance by target resource or agent, newest first")
+            .get("/:id", Self::read, "Get a Provenance resource by ID")
+            .with_state(self.provenance_service.clone())
+    }
+
+    /// Provenance of a resource's changes, or of a user's, as a FHIR
+    /// searchset bundle
+    pub async fn search(
+        State(service): State<Arc<ProvenanceService>>,
+        headers: HeaderMap,
+        Query(params): Query<ProvenanceQuery>,
+    ) -> Result<Json<Value>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+        let search = ProvenanceSearch::from_params(
+            params.target.as_deref(),
+            params.agent.as_deref(),
+            params._since,
+            params._count,
+        )
+        .map_err(|e| Self::error_response("Invalid search", e))?;
+
+        let records = service
+            .search(actor, &search)
+            .await
+            .map_err(|e| Self::error_response("Failed to search provenance", e))?;
+        let entry: Vec<Value> = records
+            .iter()
+            .map(|record| {
+                json!({
+                    "fullUrl": format!("{}/{}", PROVENANCE_RESOURCE_TYPE, record.id),
+                    "resource": record.to_fhir(),
+                    "search": { "mode": "match" },
+                })
+            })
+            .collect();
+
+        Ok(Json(json!({
+            "resourceType": BUNDLE_RESOURCE_TYPE,
+            "id": Uuid::new_v4(),
+            "meta": { "lastUpdated": Utc::now() },
+            "type": "searchset",
+            "total": entry.len(),
+            "entry": entry,
+        })))
+    }
+
+    /// One Provenance resource
+    pub async fn read(
+        State(service): State<Arc<ProvenanceService>>,
+        headers: HeaderMap,
+        Path(id): Path<i64>,
+    ) -> Result<Json<Value>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.get(actor, id).await {
+            Ok(Some(record)) => Ok(Json(record.to_fhir())),
+            Ok(None) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Provenance not found".to_string(),
+                    message: format!("No Provenance with id {}", id),
+                }),
+            )),
+            Err(e) => Err(Self::error_response("Failed to read provenance", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
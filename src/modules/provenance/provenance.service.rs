@@ -0,0 +1,276 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{DATA_OPERATION_SYSTEM, PROVENANCE_PARTICIPANT_TYPE_SYSTEM, PROVENANCE_RESOURCE_TYPE};
+use crate::modules::authorization::Action;
+use crate::modules::history::history_service::{DEFAULT_HISTORY_COUNT, MAX_HISTORY_COUNT};
+use crate::modules::history::HistoryOperation;
+use crate::modules::role::RoleService;
+
+// Import SQL queries from separate file
+use crate::modules::provenance::provenance_sql::*;
+
+/// Who changed a resource version, on whose behalf, why and from which
+/// system
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvenanceRecord {
+    pub id: i64,
+    pub target_type: String,
+    pub target_id: Uuid,
+    pub target_version: String,
+    pub activity: HistoryOperation,
+    pub recorded: DateTime<Utc>,
+    /// User making the change; `None` for the server's own changes
+    pub agent: Option<Uuid>,
+    /// Organization the user acted on behalf of
+    pub on_behalf_of: Option<Uuid>,
+    pub reason: Option<String>,
+    /// System the change came from, when not this server's API
+    pub source_system: Option<String>,
+}
+
+impl ProvenanceRecord {
+    /// The record as a FHIR Provenance resource. The user is the author;
+    /// a source system is the assembler that relayed the change.
+    pub fn to_fhir(&self) -> Value {
+        let mut agents = Vec::new();
+        if self.agent.is_some() || self.source_system.is_none() {
+            let mut author = json!({
+                "type": Self::participant_type("author"),
+                "who": match self.agent {
+                    Some(user_id) => json!({ "reference": format!("User/{}", user_id) }),
+                    None => json!({ "display": "HIMS server" }),
+                },
+            });
+            if let Some(organization_id) = self.on_behalf_of {
+                author["onBehalfOf"] = json!({ "reference": format!("Organization/{}", organization_id) });
+            }
+            agents.push(author);
+        }
+        if let Some(source) = &self.source_system {
+            agents.push(json!({
+                "type": Self::participant_type("assembler"),
+                "who": { "display": source },
+            }));
+        }
+
+        let mut provenance = json!({
+            "resourceType": PROVENANCE_RESOURCE_TYPE,
+            "id": self.id.to_string(),
+            "target": [{
+                "reference": format!("{}/{}/_history/{}", self.target_type, self.target_id, self.target_version)
+            }],
+            "recorded": self.recorded,
+            "activity": {
+                "coding": [{
+                    "system": DATA_OPERATION_SYSTEM,
+                    "code": self.activity.as_str().to_uppercase(),
+                }]
+            },
+            "agent": agents,
+        });
+        if let Some(reason) = &self.reason {
+            provenance["reason"] = json!([{ "text": reason }]);
+        }
+        provenance
+    }
+
+    fn participant_type(code: &str) -> Value {
+        json!({ "coding": [{ "system": PROVENANCE_PARTICIPANT_TYPE_SYSTEM, "code": code }] })
+    }
+}
+
+/// Criteria of a Provenance search: the changes to one resource, by one
+/// user, or both
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProvenanceSearch {
+    /// FHIR type and id of the changed resource
+    pub target: Option<(String, Uuid)>,
+    pub agent: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub count: Option<i64>,
+}
+
+impl ProvenanceSearch {
+    /// Criteria from FHIR `target=Patient/{id}` and `agent=User/{id}`
+    /// parameters. One of them is required.
+    pub fn from_params(
+        target: Option<&str>,
+        agent: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        count: Option<i64>,
+    ) -> Result<Self, HimsError> {
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid {} '{}'", name, value),
+        };
+
+        let target = target
+            .map(|value| {
+                let (resource_type, id) = value.split_once('/').ok_or_else(|| invalid("target", value))?;
+                let id = Uuid::from_str(id).map_err(|_| invalid("target", value))?;
+                Ok::<_, HimsError>((resource_type.to_string(), id))
+            })
+            .transpose()?;
+        let agent = agent
+            .map(|value| {
+                Uuid::from_str(value.strip_prefix("User/").unwrap_or(value)).map_err(|_| invalid("agent", value))
+            })
+            .transpose()?;
+        if target.is_none() && agent.is_none() {
+            return Err(HimsError::ValidationError {
+                message: "Provenance search needs a target or an agent".to_string(),
+            });
+        }
+
+        Ok(Self {
+            target,
+            agent,
+            since,
+            count,
+        })
+    }
+}
+
+/// Provenance service reading the provenance recorded with each resource
+/// version
+pub struct ProvenanceService {
+    pool: PgPool,
+    role_service: RoleService,
+}
+
+impl ProvenanceService {
+    /// Create new provenance service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Provenance matching a search in the current tenant, newest first
+    pub async fn search(&self, actor: Uuid, search: &ProvenanceSearch) -> Result<Vec<ProvenanceRecord>, HimsError> {
+        self.require(actor, Action::Audit).await?;
+
+        let count = search.count.unwrap_or(DEFAULT_HISTORY_COUNT).clamp(1, MAX_HISTORY_COUNT);
+        let (target_type, target_id) = match &search.target {
+            Some((resource_type, id)) => (Some(resource_type.as_str()), Some(*id)),
+            None => (None, None),
+        };
+        sqlx::query(SEARCH_PROVENANCE)
+            .bind(target_type)
+            .bind(target_id)
+            .bind(search.agent)
+            .bind(search.since)
+            .bind(count)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_record)
+            .collect()
+    }
+
+    /// One provenance record
+    pub async fn get(&self, actor: Uuid, id: i64) -> Result<Option<ProvenanceRecord>, HimsError> {
+        self.require(actor, Action::Audit).await?;
+
+        sqlx::query(GET_PROVENANCE)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_record)
+            .transpose()
+    }
+
+    /// Require a permission of the actor
+    async fn require(&self, actor: Uuid, action: Action) -> Result<(), HimsError> {
+        if !self.role_service.get_user_permissions(actor).await?.contains(&action) {
+            tracing::warn!("User {} lacks {} permission", actor, action);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", action),
+            });
+        }
+        Ok(())
+    }
+
+    fn row_to_record(row: &PgRow) -> Result<ProvenanceRecord, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        Ok(ProvenanceRecord {
+            id: row.try_get("id").map_err(read)?,
+            target_type: row.try_get("resource_type").map_err(read)?,
+            target_id: row.try_get("resource_id").map_err(read)?,
+            target_version: row.try_get("version_id").map_err(read)?,
+            activity: HistoryOperation::from_string(&row.try_get::<String, _>("operation").map_err(read)?),
+            recorded: row.try_get("changed_at").map_err(read)?,
+            agent: row.try_get("changed_by").map_err(read)?,
+            on_behalf_of: row.try_get("on_behalf_of").map_err(read)?,
+            reason: row.try_get("change_reason").map_err(read)?,
+            source_system: row.try_get("source_system").map_err(read)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ProvenanceRecord {
+        ProvenanceRecord {
+            id: 42,
+            target_type: "Patient".to_string(),
+            target_id: Uuid::nil(),
+            target_version: "3".to_string(),
+            activity: HistoryOperation::Update,
+            recorded: Utc::now(),
+            agent: Some(Uuid::nil()),
+            on_behalf_of: Some(Uuid::nil()),
+            reason: Some("Corrected date of birth".to_string()),
+            source_system: Some("device:MX450-1234".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_to_fhir() {
+        let provenance = record().to_fhir();
+        assert_eq!(provenance["id"], "42");
+        assert_eq!(provenance["target"][0]["reference"], format!("Patient/{}/_history/3", Uuid::nil()));
+        assert_eq!(provenance["activity"]["coding"][0]["code"], "UPDATE");
+        assert_eq!(provenance["agent"][0]["who"]["reference"], format!("User/{}", Uuid::nil()));
+        assert_eq!(provenance["agent"][0]["onBehalfOf"]["reference"], format!("Organization/{}", Uuid::nil()));
+        assert_eq!(provenance["agent"][1]["who"]["display"], "device:MX450-1234");
+        assert_eq!(provenance["reason"][0]["text"], "Corrected date of birth");
+
+        // The server's own changes still name an author
+        let provenance = ProvenanceRecord {
+            agent: None,
+            on_behalf_of: None,
+            reason: None,
+            source_system: None,
+            ..record()
+        }
+        .to_fhir();
+        assert_eq!(provenance["agent"][0]["who"]["display"], "HIMS server");
+        assert!(provenance.get("reason").is_none());
+    }
+
+    #[test]
+    fn test_search_params() {
+        let id = Uuid::new_v4();
+        let search = ProvenanceSearch::from_params(Some(&format!("Patient/{}", id)), None, None, None).unwrap();
+        assert_eq!(search.target, Some(("Patient".to_string(), id)));
+
+        let search = ProvenanceSearch::from_params(None, Some(&format!("User/{}", id)), None, None).unwrap();
+        assert_eq!(search.agent, Some(id));
+
+        assert!(ProvenanceSearch::from_params(Some("Patient"), None, None, None).is_err());
+        assert!(ProvenanceSearch::from_params(None, None, None, None).is_err());
+    }
+}
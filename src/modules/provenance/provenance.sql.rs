@@ -0,0 +1,25 @@
+//! Provenance SQL Queries
+//!
+//! This file contains all SQL queries used by the provenance service.
+//! Provenance is read from the versions the `record_resource_history`
+//! trigger records, never written here.
+
+/// Changes to resource $1/$2 and by user $3, each when given, since $4,
+/// newest first
+pub const SEARCH_PROVENANCE: &str = r#"
+    SELECT id, resource_type, resource_id, version_id, operation, changed_by, changed_at,
+           change_reason, on_behalf_of, source_system
+    FROM resource_history
+    WHERE ($1::text IS NULL OR (resource_type = $1 AND resource_id = $2))
+        AND ($3::uuid IS NULL OR changed_by = $3)
+        AND ($4::timestamptz IS NULL OR changed_at >= $4)
+    ORDER BY id DESC
+    LIMIT $5
+"#;
+
+pub const GET_PROVENANCE: &str = r#"
+    SELECT id, resource_type, resource_id, version_id, operation, changed_by, changed_at,
+           change_reason, on_behalf_of, source_system
+    FROM resource_history
+    WHERE id = $1
+"#;
@@ -0,0 +1,56 @@
+//! Quality Module
+//!
+//! This module evaluates clinical quality measures (eCQMs) including:
+//! - Proportion measures configured as cohort criteria for each population
+//! - Initial population, denominator, exclusion and numerator counts over a cohort or all patients
+//! - Results reported as FHIR MeasureReports and QRDA Category III documents
+
+#[path = "quality.controller.rs"]
+pub mod quality_controller;
+#[path = "quality.service.rs"]
+pub mod quality_service;
+#[path = "quality.measures.rs"]
+pub mod quality_measures;
+#[path = "quality.format.rs"]
+pub mod quality_format;
+#[path = "quality.sql.rs"]
+pub mod quality_sql;
+
+pub use quality_controller::QualityController;
+pub use quality_format::ReportingOrganization;
+pub use quality_measures::{ImprovementNotation, MeasurePopulations, PopulationCounts};
+pub use quality_service::{MeasureResult, QualityConfig, QualityMeasure, QualityService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Quality Module Configuration
+pub struct QualityModule {
+    pub service: Arc<QualityService>,
+    pub controller: Arc<QualityController>,
+}
+
+impl QualityModule {
+    /// Create a new Quality Module with dependency injection
+    pub fn new(db_pool: PgPool, config: QualityConfig) -> Self {
+        let service = Arc::new(QualityService::new(db_pool, config));
+        let controller = Arc::new(QualityController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<QualityService> {
+        self.service.clone()
+    }
+}
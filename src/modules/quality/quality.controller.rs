@@ -0,0 +1,229 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::quality::quality_measures::QualityMeasureRequest;
+use crate::modules::quality::quality_service::{EvaluateMeasureRequest, MeasureResult, QrdaQuery, QualityMeasure};
+use crate::modules::quality::QualityService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Quality controller for clinical quality measures and their reports
+pub struct QualityController {
+    quality_service: Arc<QualityService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl QualityController {
+    /// Create new controller with injected service
+    pub fn new(quality_service: Arc<QualityService>) -> Self {
+        Self { quality_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/templates", Self::templates, "List measures to start from")
+            .post("/measures", Self::create_measure, "Configure a quality measure")
+            .get("/measures", Self::list_measures, "List quality measures")
+            .get("/measures/:id", Self::get_measure, "Get a quality measure")
+            .put("/measures/:id", Self::update_measure, "Replace a quality measure")
+            .post("/measures/:id/evaluate", Self::evaluate, "Evaluate a quality measure")
+            .get("/measures/:id/results", Self::list_results, "List a quality measure's results")
+            .get("/results/:id/measure-report", Self::measure_report, "Get a result as a FHIR MeasureReport")
+            .get("/qrda", Self::qrda, "Export results as a QRDA Category III document")
+            .with_state(self.quality_service.clone())
+    }
+
+    /// List measures to start from
+    pub async fn templates(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<QualityMeasureRequest>>, ErrorReply> {
+        Self::actor(&headers)?;
+        Ok(Json(service.templates()))
+    }
+
+    /// Configure a quality measure
+    pub async fn create_measure(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+        Json(payload): Json<QualityMeasureRequest>,
+    ) -> Result<(StatusCode, Json<QualityMeasure>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.create_measure(payload, actor).await {
+            Ok(measure) => Ok((StatusCode::CREATED, Json(measure))),
+            Err(e) => Err(Self::error_response("Failed to configure quality measure", e)),
+        }
+    }
+
+    /// List quality measures
+    pub async fn list_measures(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<QualityMeasure>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_measures(actor).await {
+            Ok(measures) => Ok(Json(measures)),
+            Err(e) => Err(Self::error_response("Failed to list quality measures", e)),
+        }
+    }
+
+    /// Get a quality measure
+    pub async fn get_measure(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<QualityMeasure>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.get_measure(id, actor).await {
+            Ok(Some(measure)) => Ok(Json(measure)),
+            Ok(None) => Err(Self::not_found("Quality measure", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve quality measure", e)),
+        }
+    }
+
+    /// Replace a quality measure
+    pub async fn update_measure(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<QualityMeasureRequest>,
+    ) -> Result<Json<QualityMeasure>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.update_measure(id, payload, actor).await {
+            Ok(Some(measure)) => Ok(Json(measure)),
+            Ok(None) => Err(Self::not_found("Quality measure", id)),
+            Err(e) => Err(Self::error_response("Failed to update quality measure", e)),
+        }
+    }
+
+    /// Evaluate a quality measure, over a cohort if one is given
+    pub async fn evaluate(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        payload: Option<Json<EvaluateMeasureRequest>>,
+    ) -> Result<Json<MeasureResult>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+        let request = payload.map(|Json(request)| request).unwrap_or_default();
+
+        match service.evaluate(id, &request, actor).await {
+            Ok(Some(result)) => Ok(Json(result)),
+            Ok(None) => Err(Self::not_found("Quality measure", id)),
+            Err(e) => Err(Self::error_response("Failed to evaluate quality measure", e)),
+        }
+    }
+
+    /// List a quality measure's latest results
+    pub async fn list_results(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<MeasureResult>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_results(id, actor).await {
+            Ok(results) => Ok(Json(results)),
+            Err(e) => Err(Self::error_response("Failed to list measure results", e)),
+        }
+    }
+
+    /// Get a result as a FHIR MeasureReport
+    pub async fn measure_report(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Value>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.measure_report(id, actor).await {
+            Ok(Some(report)) => Ok(Json(report)),
+            Ok(None) => Err(Self::not_found("Measure result", id)),
+            Err(e) => Err(Self::error_response("Failed to build MeasureReport", e)),
+        }
+    }
+
+    /// Export results as a QRDA Category III document
+    pub async fn qrda(
+        State(service): State<Arc<QualityService>>,
+        headers: HeaderMap,
+        Query(query): Query<QrdaQuery>,
+    ) -> Result<Response, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.qrda(&query, actor).await {
+            Ok(document) => Ok((
+                [
+                    (header::CONTENT_TYPE, HeaderValue::from_static("application/xml")),
+                    (header::CONTENT_DISPOSITION, HeaderValue::from_static("attachment; filename=\"qrda-iii.xml\"")),
+                    (header::CACHE_CONTROL, HeaderValue::from_static("private, no-store")),
+                ],
+                document,
+            )
+                .into_response()),
+            Err(e) => Err(Self::error_response("Failed to export QRDA III document", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(what: &str, id: Uuid) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", what),
+                message: format!("{} with id {} not found", what, id),
+            }),
+        )
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,355 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::exporters::ccda::{escape_xml, timestamp, CONFIDENTIALITY_OID, LOINC_OID, SNOMED_CT_OID};
+use crate::models::constants::{
+    MEASURE_IMPROVEMENT_NOTATION_SYSTEM, MEASURE_POPULATION_SYSTEM, MEASURE_REPORT_RESOURCE_TYPE,
+};
+use crate::modules::quality::quality_service::{MeasureResult, QualityMeasure};
+
+/// QRDA Category III Report, and the CMS implementation guide's version of
+/// it, with the extensions of the versions used
+pub const QRDA_III_REPORT_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.27.1.1", "2017-06-01");
+pub const QRDA_III_CMS_REPORT_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.27.1.2", "2020-12-01");
+/// Measure Section, QDM-based and QRDA III
+pub const MEASURE_SECTION_TEMPLATE: &str = "2.16.840.1.113883.10.20.24.2.2";
+pub const QRDA_III_MEASURE_SECTION_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.27.2.1", "2017-06-01");
+/// Measure Reference and Results, the organizer of a measure's data
+pub const MEASURE_REFERENCE_TEMPLATE: &str = "2.16.840.1.113883.10.20.24.3.98";
+pub const MEASURE_REFERENCE_RESULTS_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.27.3.1", "2016-09-01");
+/// Measure Data, a population's count, and the Aggregate Count it has
+pub const MEASURE_DATA_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.27.3.5", "2016-09-01");
+pub const AGGREGATE_COUNT_TEMPLATE: &str = "2.16.840.1.113883.10.20.27.3.3";
+/// Performance Rate for Proportion Measure
+pub const PERFORMANCE_RATE_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.27.3.14", "2020-12-01");
+/// Reporting Parameters Act, which gives the measurement period
+pub const REPORTING_PARAMETERS_TEMPLATE: &str = "2.16.840.1.113883.10.20.17.3.8";
+pub const QRDA_III_REPORTING_PARAMETERS_TEMPLATE: (&str, &str) = ("2.16.840.1.113883.10.20.27.3.23", "2016-09-01");
+
+/// Identifiers of eCQMs by their version-specific ID
+const ECQM_VERSION_OID: &str = "2.16.840.1.113883.4.738";
+/// National Provider Identifier and Tax Identification Number
+const NPI_OID: &str = "2.16.840.1.113883.4.6";
+const TIN_OID: &str = "2.16.840.1.113883.4.2";
+/// HL7 ActCode, of the population codes, and ObservationMethod
+const ACT_CODE_OID: &str = "2.16.840.1.113883.5.4";
+const OBSERVATION_METHOD_OID: &str = "2.16.840.1.113883.5.84";
+
+/// The organization reporting measures, as identified to the programme it
+/// reports to
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportingOrganization {
+    pub name: Option<String>,
+    pub tin: Option<String>,
+    pub npi: Option<String>,
+}
+
+/// A result's populations: FHIR code, QRDA code and count
+fn populations(result: &MeasureResult) -> [(&'static str, &'static str, i64); 4] {
+    let counts = &result.counts;
+    [
+        ("initial-population", "IPOP", counts.initial_population),
+        ("denominator", "DENOM", counts.denominator),
+        ("denominator-exclusion", "DENEX", counts.denominator_exclusion),
+        ("numerator", "NUMER", counts.numerator),
+    ]
+}
+
+/// A result as a FHIR summary MeasureReport
+pub fn measure_report(measure: &QualityMeasure, result: &MeasureResult) -> Value {
+    let population: Vec<Value> = populations(result)
+        .iter()
+        .map(|(code, _, count)| {
+            json!({
+                "code": { "coding": [{ "system": MEASURE_POPULATION_SYSTEM, "code": code }] },
+                "count": count,
+            })
+        })
+        .collect();
+    let mut group = json!({ "population": population });
+    if let Some(rate) = result.performance_rate {
+        group["measureScore"] = json!({ "value": rate });
+    }
+
+    let mut report = json!({
+        "resourceType": MEASURE_REPORT_RESOURCE_TYPE,
+        "id": result.id,
+        "identifier": [{ "value": measure.code }],
+        "status": "complete",
+        "type": "summary",
+        "measure": measure.url.clone().unwrap_or_else(|| format!("Measure/{}", measure.code)),
+        "date": result.evaluated_at,
+        "period": { "start": result.period_start, "end": result.period_end },
+        "improvementNotation": {
+            "coding": [{
+                "system": MEASURE_IMPROVEMENT_NOTATION_SYSTEM,
+                "code": measure.improvement_notation.as_str(),
+            }],
+        },
+        "group": [group],
+    });
+    if let Some(cohort_id) = result.cohort_id {
+        report["subject"] = json!({ "reference": format!("Group/{}", cohort_id) });
+    }
+    report
+}
+
+fn day(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn template(template: (&str, &str)) -> String {
+    format!("<templateId root=\"{}\" extension=\"{}\"/>", template.0, template.1)
+}
+
+/// Results of measures as a QRDA Category III aggregate report. Each
+/// measure must carry its eCQM version-specific ID; the measurement period
+/// given spans every result's.
+pub fn qrda_category_three(
+    results: &[(QualityMeasure, MeasureResult)],
+    organization: &ReportingOrganization,
+    now: DateTime<Utc>,
+) -> Result<String, String> {
+    let (Some(start), Some(end)) = (
+        results.iter().map(|(_, result)| result.period_start).min(),
+        results.iter().map(|(_, result)| result.period_end).max(),
+    ) else {
+        return Err("A QRDA III report needs at least one measure result".to_string());
+    };
+    if let Some((measure, _)) = results.iter().find(|(measure, _)| measure.version_specific_id.is_none()) {
+        return Err(format!("Measure {} has no eCQM version-specific ID to report it under", measure.code));
+    }
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <ClinicalDocument xmlns=\"urn:hl7-org:v3\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\
+         <realmCode code=\"US\"/><typeId root=\"2.16.840.1.113883.1.3\" extension=\"POCD_HD000040\"/>",
+    );
+    xml.push_str(&template(QRDA_III_REPORT_TEMPLATE));
+    xml.push_str(&template(QRDA_III_CMS_REPORT_TEMPLATE));
+    xml.push_str(&format!(
+        "<id root=\"{}\"/>\
+         <code code=\"55184-6\" codeSystem=\"{}\" codeSystemName=\"LOINC\" \
+         displayName=\"Quality Reporting Document Architecture Calculated Summary Report\"/>\
+         <title>QRDA Calculated Summary Report</title>\
+         <effectiveTime value=\"{}\"/>\
+         <confidentialityCode code=\"N\" codeSystem=\"{}\"/>\
+         <languageCode code=\"en\"/>\
+         <recordTarget><patientRole><id nullFlavor=\"NA\"/></patientRole></recordTarget>",
+        Uuid::new_v4(),
+        LOINC_OID,
+        timestamp(now),
+        CONFIDENTIALITY_OID
+    ));
+
+    let tin = match &organization.tin {
+        Some(tin) => format!("<id root=\"{}\" extension=\"{}\"/>", TIN_OID, escape_xml(tin)),
+        None => "<id nullFlavor=\"UNK\"/>".to_string(),
+    };
+    let name = escape_xml(organization.name.as_deref().unwrap_or_default());
+    let organization_xml = format!("<representedOrganization>{}<name>{}</name></representedOrganization>", tin, name);
+    let npi = match &organization.npi {
+        Some(npi) => format!("<id root=\"{}\" extension=\"{}\"/>", NPI_OID, escape_xml(npi)),
+        None => "<id nullFlavor=\"UNK\"/>".to_string(),
+    };
+    xml.push_str(&format!(
+        "<author><time value=\"{}\"/><assignedAuthor>{}{}</assignedAuthor></author>",
+        timestamp(now),
+        npi,
+        organization_xml
+    ));
+    xml.push_str(&format!(
+        "<custodian><assignedCustodian><representedCustodianOrganization>{}<name>{}</name>\
+         </representedCustodianOrganization></assignedCustodian></custodian>",
+        tin, name
+    ));
+    xml.push_str(&format!(
+        "<legalAuthenticator><time value=\"{}\"/><signatureCode code=\"S\"/><assignedEntity>{}{}</assignedEntity>\
+         </legalAuthenticator>",
+        timestamp(now),
+        npi,
+        organization_xml
+    ));
+
+    xml.push_str("<component><structuredBody><component><section>");
+    xml.push_str(&format!("<templateId root=\"{}\"/>", MEASURE_SECTION_TEMPLATE));
+    xml.push_str(&template(QRDA_III_MEASURE_SECTION_TEMPLATE));
+    xml.push_str(&format!(
+        "<code code=\"55186-1\" codeSystem=\"{}\" codeSystemName=\"LOINC\" displayName=\"Measure Section\"/>\
+         <title>Measure Section</title>",
+        LOINC_OID
+    ));
+    xml.push_str("<text><table><thead><tr><th>Measure</th><th>Initial population</th><th>Denominator</th>");
+    xml.push_str("<th>Exclusions</th><th>Numerator</th><th>Performance rate</th></tr></thead><tbody>");
+    for (measure, result) in results {
+        let counts = &result.counts;
+        xml.push_str(&format!(
+            "<tr><td>{} {}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_xml(&measure.code),
+            escape_xml(&measure.name),
+            counts.initial_population,
+            counts.denominator,
+            counts.denominator_exclusion,
+            counts.numerator,
+            result.performance_rate.map(|rate| format!("{:.4}", rate)).unwrap_or_else(|| "NA".to_string())
+        ));
+    }
+    xml.push_str("</tbody></table></text>");
+
+    xml.push_str(&format!(
+        "<entry typeCode=\"DRIV\"><act classCode=\"ACT\" moodCode=\"EVN\"><templateId root=\"{}\"/>{}\
+         <id root=\"{}\"/><code code=\"252116004\" codeSystem=\"{}\" displayName=\"Observation Parameters\"/>\
+         <effectiveTime><low value=\"{}\"/><high value=\"{}\"/></effectiveTime></act></entry>",
+        REPORTING_PARAMETERS_TEMPLATE,
+        template(QRDA_III_REPORTING_PARAMETERS_TEMPLATE),
+        Uuid::new_v4(),
+        SNOMED_CT_OID,
+        day(start),
+        day(end)
+    ));
+    for (measure, result) in results {
+        xml.push_str(&measure_entry(measure, result));
+    }
+    xml.push_str("</section></component></structuredBody></component></ClinicalDocument>");
+    Ok(xml)
+}
+
+/// A measure's Measure Reference and Results organizer: a Measure Data
+/// observation per population, and the performance rate
+fn measure_entry(measure: &QualityMeasure, result: &MeasureResult) -> String {
+    let mut xml = format!(
+        "<entry><organizer classCode=\"CLUSTER\" moodCode=\"EVN\"><templateId root=\"{}\"/>{}\
+         <id root=\"{}\"/><statusCode code=\"completed\"/>\
+         <reference typeCode=\"REFR\"><externalDocument classCode=\"DOC\" moodCode=\"EVN\">\
+         <id root=\"{}\" extension=\"{}\"/>\
+         <code code=\"57024-2\" codeSystem=\"{}\" codeSystemName=\"LOINC\" \
+         displayName=\"Health Quality Measure Document\"/>\
+         <text>{}</text></externalDocument></reference>",
+        MEASURE_REFERENCE_TEMPLATE,
+        template(MEASURE_REFERENCE_RESULTS_TEMPLATE),
+        result.id,
+        ECQM_VERSION_OID,
+        escape_xml(measure.version_specific_id.as_deref().unwrap_or_default()),
+        LOINC_OID,
+        escape_xml(&measure.name)
+    );
+    for (_, code, count) in populations(result) {
+        xml.push_str(&format!(
+            "<component><observation classCode=\"OBS\" moodCode=\"EVN\">{}\
+             <code code=\"ASSERTION\" codeSystem=\"{}\" codeSystemName=\"ActCode\" displayName=\"Assertion\"/>\
+             <statusCode code=\"completed\"/>\
+             <value xsi:type=\"CD\" code=\"{}\" codeSystem=\"{}\" codeSystemName=\"ActCode\"/>\
+             <entryRelationship typeCode=\"SUBJ\" inversionInd=\"true\">\
+             <observation classCode=\"OBS\" moodCode=\"EVN\"><templateId root=\"{}\"/>\
+             <code code=\"MSRAGG\" codeSystem=\"{}\" codeSystemName=\"ActCode\" displayName=\"rate aggregation\"/>\
+             <value xsi:type=\"INT\" value=\"{}\"/>\
+             <methodCode code=\"COUNT\" codeSystem=\"{}\" codeSystemName=\"ObservationMethod\" displayName=\"Count\"/>\
+             </observation></entryRelationship></observation></component>",
+            template(MEASURE_DATA_TEMPLATE),
+            ACT_CODE_OID,
+            code,
+            ACT_CODE_OID,
+            AGGREGATE_COUNT_TEMPLATE,
+            ACT_CODE_OID,
+            count,
+            OBSERVATION_METHOD_OID
+        ));
+    }
+    let rate = match result.performance_rate {
+        Some(rate) => format!("<value xsi:type=\"REAL\" value=\"{:.6}\"/>", rate),
+        None => "<value xsi:type=\"REAL\" nullFlavor=\"NA\"/>".to_string(),
+    };
+    xml.push_str(&format!(
+        "<component><observation classCode=\"OBS\" moodCode=\"EVN\">{}\
+         <code code=\"72510-1\" codeSystem=\"{}\" codeSystemName=\"LOINC\" displayName=\"Performance Rate\"/>\
+         <statusCode code=\"completed\"/>{}</observation></component>",
+        template(PERFORMANCE_RATE_TEMPLATE),
+        LOINC_OID,
+        rate
+    ));
+    xml.push_str("</organizer></entry>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::quality::quality_measures::{measure_templates, ImprovementNotation, PopulationCounts};
+
+    fn measure() -> QualityMeasure {
+        let template = measure_templates().remove(1);
+        QualityMeasure {
+            id: Uuid::new_v4(),
+            code: template.code,
+            name: template.name,
+            description: template.description,
+            url: None,
+            version_specific_id: Some("2c928085-7198-38ee-0171-9da6456007ab".to_string()),
+            improvement_notation: ImprovementNotation::Increase,
+            period_days: 365,
+            populations: template.populations,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn evaluated(measure: &QualityMeasure) -> MeasureResult {
+        let counts =
+            PopulationCounts { initial_population: 120, denominator: 120, denominator_exclusion: 20, numerator: 75 };
+        MeasureResult {
+            id: Uuid::new_v4(),
+            measure_id: measure.id,
+            cohort_id: None,
+            period_start: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            period_end: NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+            counts,
+            performance_rate: counts.performance_rate(),
+            evaluated_by: None,
+            evaluated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_measure_report() {
+        let measure = measure();
+        let result = evaluated(&measure);
+        let report = measure_report(&measure, &result);
+        assert_eq!(report["resourceType"], "MeasureReport");
+        assert_eq!(report["measure"], "Measure/CMS165");
+        assert_eq!(report["period"]["start"], "2025-01-01");
+        assert_eq!(report["improvementNotation"]["coding"][0]["code"], "increase");
+        let populations = report["group"][0]["population"].as_array().unwrap();
+        assert_eq!(populations[2]["code"]["coding"][0]["code"], "denominator-exclusion");
+        assert_eq!(populations[2]["count"], 20);
+        assert_eq!(report["group"][0]["measureScore"]["value"], 0.75);
+    }
+
+    #[test]
+    fn test_qrda_category_three() {
+        let measure = measure();
+        let result = evaluated(&measure);
+        let organization = ReportingOrganization {
+            name: Some("Riverside Clinic & Labs".to_string()),
+            tin: Some("123456789".to_string()),
+            npi: None,
+        };
+        let xml = qrda_category_three(&[(measure.clone(), result)], &organization, Utc::now()).unwrap();
+        roxmltree::Document::parse(&xml).unwrap();
+        assert!(xml.contains("<templateId root=\"2.16.840.1.113883.10.20.27.1.1\" extension=\"2017-06-01\"/>"));
+        assert!(xml.contains("extension=\"2c928085-7198-38ee-0171-9da6456007ab\""));
+        assert!(xml.contains("<low value=\"20250101\"/><high value=\"20251231\"/>"));
+        assert!(xml.contains("code=\"DENEX\""));
+        assert!(xml.contains("<value xsi:type=\"INT\" value=\"75\"/>"));
+        assert!(xml.contains("<value xsi:type=\"REAL\" value=\"0.750000\"/>"));
+        assert!(xml.contains("Riverside Clinic &amp; Labs"));
+
+        let mut unversioned = measure;
+        unversioned.version_specific_id = None;
+        let result = evaluated(&unversioned);
+        assert!(qrda_category_three(&[(unversioned, result)], &organization, Utc::now()).is_err());
+        assert!(qrda_category_three(&[], &organization, Utc::now()).is_err());
+    }
+}
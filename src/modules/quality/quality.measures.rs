@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::modules::cohort::CohortCriterion;
+
+/// Whether a higher or a lower score is better
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImprovementNotation {
+    Increase,
+    Decrease,
+}
+
+impl ImprovementNotation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImprovementNotation::Increase => "increase",
+            ImprovementNotation::Decrease => "decrease",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "increase" => Some(ImprovementNotation::Increase),
+            "decrease" => Some(ImprovementNotation::Decrease),
+            _ => None,
+        }
+    }
+}
+
+/// The populations of a proportion measure, each as cohort criteria. The
+/// denominator is drawn from the initial population, the whole of it if no
+/// denominator is given; the numerator from the denominator less its
+/// exclusions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeasurePopulations {
+    pub initial_population: CohortCriterion,
+    pub denominator: Option<CohortCriterion>,
+    pub denominator_exclusion: Option<CohortCriterion>,
+    pub numerator: CohortCriterion,
+}
+
+/// Patients counted in each population of a measure
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PopulationCounts {
+    pub initial_population: i64,
+    pub denominator: i64,
+    pub denominator_exclusion: i64,
+    pub numerator: i64,
+}
+
+impl PopulationCounts {
+    /// Numerator over the denominator less its exclusions; `None` if that
+    /// leaves no one
+    pub fn performance_rate(&self) -> Option<f64> {
+        let eligible = self.denominator - self.denominator_exclusion;
+        (eligible > 0).then(|| self.numerator as f64 / eligible as f64)
+    }
+}
+
+impl MeasurePopulations {
+    pub fn check(&self) -> Result<(), String> {
+        let populations = [
+            ("initial population", Some(&self.initial_population)),
+            ("denominator", self.denominator.as_ref()),
+            ("denominator exclusion", self.denominator_exclusion.as_ref()),
+            ("numerator", Some(&self.numerator)),
+        ];
+        for (name, criterion) in populations {
+            if let Some(criterion) = criterion {
+                criterion.check().map_err(|e| format!("The {}: {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Push a query counting the patients of each population, as the
+    /// columns of `PopulationCounts`. Only patients of the initial
+    /// population, and of the cohort if one is given, are looked at further.
+    pub fn push_counts(&self, query: &mut QueryBuilder<'_, Postgres>, cohort: Option<Uuid>) -> Result<(), String> {
+        self.check()?;
+        query.push(
+            "SELECT count(*) AS initial_population, \
+             count(*) FILTER (WHERE m.denominator) AS denominator, \
+             count(*) FILTER (WHERE m.denominator AND m.exclusion) AS denominator_exclusion, \
+             count(*) FILTER (WHERE m.denominator AND NOT m.exclusion AND m.numerator) AS numerator FROM (SELECT ",
+        );
+        let columns = [
+            (self.denominator.as_ref(), "true", "denominator"),
+            (self.denominator_exclusion.as_ref(), "false", "exclusion"),
+            (Some(&self.numerator), "false", "numerator"),
+        ];
+        for (i, (criterion, absent, column)) in columns.into_iter().enumerate() {
+            if i > 0 {
+                query.push(", ");
+            }
+            match criterion {
+                Some(criterion) => {
+                    // Criteria on a latest result have none for patients
+                    // without results, which counts as not met
+                    query.push("COALESCE(");
+                    criterion.push_condition(query)?;
+                    query.push(format!(", false) AS {}", column));
+                }
+                None => {
+                    query.push(format!("{} AS {}", absent, column));
+                }
+            }
+        }
+        query.push(" FROM patients p WHERE p.active = true AND COALESCE(");
+        self.initial_population.push_condition(query)?;
+        query.push(", false)");
+        if let Some(cohort) = cohort {
+            query
+                .push(" AND EXISTS (SELECT 1 FROM cohort_members cm WHERE cm.patient_id = p.id AND cm.cohort_id = ")
+                .push_bind(cohort)
+                .push(")");
+        }
+        query.push(") m");
+        Ok(())
+    }
+}
+
+/// A measure to configure
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QualityMeasureRequest {
+    /// Short code the measure is known by, e.g. `CMS122`
+    pub code: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Canonical URL of the measure, which MeasureReports refer to
+    pub url: Option<String>,
+    /// The eCQM's version-specific identifier, which QRDA III documents
+    /// refer to
+    pub version_specific_id: Option<String>,
+    pub improvement_notation: ImprovementNotation,
+    /// Days of the measurement period, ending on the day a measure is
+    /// evaluated; the criteria's look-backs should match it
+    #[serde(default = "default_period_days")]
+    pub period_days: i32,
+    pub populations: MeasurePopulations,
+    #[serde(default = "default_true")]
+    pub active: bool,
+}
+
+fn default_period_days() -> i32 {
+    365
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl QualityMeasureRequest {
+    pub fn check(&mut self) -> Result<(), String> {
+        self.code = self.code.trim().to_string();
+        self.name = self.name.trim().to_string();
+        if self.code.is_empty() || self.name.is_empty() {
+            return Err("A measure needs a code and a name".to_string());
+        }
+        if !(1..=3660).contains(&self.period_days) {
+            return Err("A measurement period is 1 to 3660 days".to_string());
+        }
+        self.populations.check()
+    }
+}
+
+fn criterion(value: serde_json::Value) -> CohortCriterion {
+    serde_json::from_value(value).expect("built-in measure criteria are valid")
+}
+
+/// Measures offered to start from, after the CMS eCQMs of the same
+/// intent. Value sets are cut down to a few common codes, so they should be
+/// widened to the codes a site records before results are reported.
+pub fn measure_templates() -> Vec<QualityMeasureRequest> {
+    let diabetes = json!({
+        "type": "diagnosis",
+        "active_only": true,
+        "codes": [
+            "http://snomed.info/sct|73211009",
+            "http://snomed.info/sct|44054006",
+            "http://snomed.info/sct|46635009",
+            "http://hl7.org/fhir/sid/icd-10-cm|E11.9",
+            "http://hl7.org/fhir/sid/icd-10-cm|E10.9",
+        ],
+    });
+    let hypertension = json!({
+        "type": "diagnosis",
+        "active_only": true,
+        "codes": ["http://snomed.info/sct|38341003", "http://hl7.org/fhir/sid/icd-10-cm|I10"],
+    });
+    let hba1c = "http://loinc.org|4548-4";
+
+    vec![
+        QualityMeasureRequest {
+            code: "CMS122".to_string(),
+            name: "Diabetes: Hemoglobin A1c (HbA1c) Poor Control (> 9%)".to_string(),
+            description: Some(
+                "Patients 18-75 years of age with diabetes whose most recent HbA1c in the period was over 9%, \
+                 or who had no HbA1c in the period. A lower rate is better."
+                    .to_string(),
+            ),
+            url: None,
+            version_specific_id: None,
+            improvement_notation: ImprovementNotation::Decrease,
+            period_days: 365,
+            populations: MeasurePopulations {
+                initial_population: criterion(json!({
+                    "type": "all",
+                    "criteria": [{"type": "age", "min": 18, "max": 75}, diabetes],
+                })),
+                denominator: None,
+                denominator_exclusion: None,
+                numerator: criterion(json!({
+                    "type": "any",
+                    "criteria": [
+                        {"type": "lab_value", "code": hba1c, "comparator": "gt", "value": 9.0, "within_days": 365,
+                         "latest": true},
+                        // Every HbA1c result is at least zero, so this is
+                        // no result in the period
+                        {"type": "not", "criterion": {"type": "lab_value", "code": hba1c, "comparator": "ge",
+                                                      "value": 0.0, "within_days": 365}},
+                    ],
+                })),
+            },
+            active: true,
+        },
+        QualityMeasureRequest {
+            code: "CMS165".to_string(),
+            name: "Controlling High Blood Pressure".to_string(),
+            description: Some(
+                "Patients 18-85 years of age with hypertension whose most recent blood pressure in the period \
+                 was below 140/90 mmHg."
+                    .to_string(),
+            ),
+            url: None,
+            version_specific_id: None,
+            improvement_notation: ImprovementNotation::Increase,
+            period_days: 365,
+            populations: MeasurePopulations {
+                initial_population: criterion(json!({
+                    "type": "all",
+                    "criteria": [{"type": "age", "min": 18, "max": 85}, hypertension],
+                })),
+                denominator: None,
+                denominator_exclusion: None,
+                numerator: criterion(json!({
+                    "type": "all",
+                    "criteria": [
+                        {"type": "lab_value", "code": "http://loinc.org|8480-6", "comparator": "lt", "value": 140.0,
+                         "within_days": 365, "latest": true},
+                        {"type": "lab_value", "code": "http://loinc.org|8462-4", "comparator": "lt", "value": 90.0,
+                         "within_days": 365, "latest": true},
+                    ],
+                })),
+            },
+            active: true,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_templates() {
+        for mut template in measure_templates() {
+            template.check().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_count_populations() {
+        let mut template = measure_templates().remove(1);
+        template.populations.denominator_exclusion = Some(CohortCriterion::Deceased { deceased: true });
+        let mut query = QueryBuilder::new("");
+        template.populations.push_counts(&mut query, Some(Uuid::nil())).unwrap();
+        let sql = query.sql();
+        assert!(sql.starts_with("SELECT count(*) AS initial_population,"));
+        assert!(sql.contains("true AS denominator, COALESCE(COALESCE(p.deceased, false) = $1, false) AS exclusion"));
+        assert!(sql.contains("FROM patients p WHERE p.active = true AND COALESCE(((p.birth_date IS NOT NULL"));
+        assert!(sql.ends_with("cm.cohort_id = $16)) m"));
+
+        let counts =
+            PopulationCounts { initial_population: 10, denominator: 10, denominator_exclusion: 2, numerator: 6 };
+        assert_eq!(counts.performance_rate(), Some(0.75));
+        assert_eq!(PopulationCounts::default().performance_rate(), None);
+    }
+}
@@ -0,0 +1,417 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::Action;
+use crate::modules::quality::quality_format::{measure_report, qrda_category_three, ReportingOrganization};
+use crate::modules::quality::quality_measures::{
+    measure_templates, ImprovementNotation, MeasurePopulations, PopulationCounts, QualityMeasureRequest,
+};
+use crate::modules::role::RoleService;
+
+// Import SQL queries
+use crate::modules::quality::quality_sql::*;
+
+/// Longest an evaluation of a measure may run
+const STATEMENT_TIMEOUT: &str = "120s";
+
+/// Most results one QRDA III report may hold
+const MAX_QRDA_RESULTS: usize = 100;
+
+/// Quality measure settings
+#[derive(Debug, Clone, Default)]
+pub struct QualityConfig {
+    /// The organization QRDA III reports are sent by
+    pub organization: ReportingOrganization,
+}
+
+impl QualityConfig {
+    /// Settings from `QUALITY_REPORTING_ORGANIZATION`, `QUALITY_REPORTING_TIN`
+    /// and `QUALITY_REPORTING_NPI`
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        Self {
+            organization: ReportingOrganization {
+                name: var("QUALITY_REPORTING_ORGANIZATION"),
+                tin: var("QUALITY_REPORTING_TIN"),
+                npi: var("QUALITY_REPORTING_NPI"),
+            },
+        }
+    }
+}
+
+/// A configured quality measure
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityMeasure {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub version_specific_id: Option<String>,
+    pub improvement_notation: ImprovementNotation,
+    pub period_days: i32,
+    pub populations: MeasurePopulations,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A measure's populations as evaluated over a period
+#[derive(Debug, Clone, Serialize)]
+pub struct MeasureResult {
+    pub id: Uuid,
+    pub measure_id: Uuid,
+    /// The cohort the measure was evaluated over; every patient if `None`
+    pub cohort_id: Option<Uuid>,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    #[serde(flatten)]
+    pub counts: PopulationCounts,
+    pub performance_rate: Option<f64>,
+    pub evaluated_by: Option<Uuid>,
+    pub evaluated_at: DateTime<Utc>,
+}
+
+/// What to evaluate a measure over
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EvaluateMeasureRequest {
+    /// A saved cohort, whose members as of its latest evaluation are the
+    /// patients looked at
+    pub cohort_id: Option<Uuid>,
+}
+
+/// Results to put in a QRDA III report, comma-separated
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QrdaQuery {
+    pub results: String,
+}
+
+fn measure_from_row(row: &PgRow) -> Result<QualityMeasure, HimsError> {
+    let populations = serde_json::from_value(row.get("populations")).map_err(|e| HimsError::InternalError {
+        message: format!("Stored measure populations are not valid: {}", e),
+    })?;
+    Ok(QualityMeasure {
+        id: row.get("id"),
+        code: row.get("code"),
+        name: row.get("name"),
+        description: row.get("description"),
+        url: row.get("url"),
+        version_specific_id: row.get("version_specific_id"),
+        improvement_notation: ImprovementNotation::from_string(&row.get::<String, _>("improvement_notation"))
+            .unwrap_or(ImprovementNotation::Increase),
+        period_days: row.get("period_days"),
+        populations,
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+fn result_from_row(row: &PgRow) -> MeasureResult {
+    let counts = PopulationCounts {
+        initial_population: row.get("initial_population"),
+        denominator: row.get("denominator"),
+        denominator_exclusion: row.get("denominator_exclusion"),
+        numerator: row.get("numerator"),
+    };
+    MeasureResult {
+        id: row.get("id"),
+        measure_id: row.get("measure_id"),
+        cohort_id: row.get("cohort_id"),
+        period_start: row.get("period_start"),
+        period_end: row.get("period_end"),
+        counts,
+        performance_rate: counts.performance_rate(),
+        evaluated_by: row.get("evaluated_by"),
+        evaluated_at: row.get("evaluated_at"),
+    }
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+/// A measure's query that ran out of time is the measure's fault, not the
+/// server's
+fn evaluation_error(e: sqlx::Error) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.code().as_deref() == Some("57014") => HimsError::ValidationError {
+            message: format!("The measure took longer than {} to evaluate", STATEMENT_TIMEOUT),
+        },
+        _ => database_error(e),
+    }
+}
+
+/// Quality measure service: configurable proportion measures over cohort
+/// criteria, evaluated into population counts and reported as FHIR
+/// MeasureReports or QRDA Category III documents
+pub struct QualityService {
+    pool: PgPool,
+    role_service: RoleService,
+    config: QualityConfig,
+}
+
+impl QualityService {
+    /// Create new quality measure service
+    pub fn new(pool: PgPool, config: QualityConfig) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+            config,
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Measures to start from when configuring measures
+    pub fn templates(&self) -> Vec<QualityMeasureRequest> {
+        measure_templates()
+    }
+
+    /// Configure a measure
+    pub async fn create_measure(
+        &self,
+        mut request: QualityMeasureRequest,
+        actor: Uuid,
+    ) -> Result<QualityMeasure, HimsError> {
+        request.check().map_err(validation_error)?;
+        self.require(actor, &[Action::Configure]).await?;
+
+        let row = Self::bind_measure(sqlx::query(INSERT_MEASURE).bind(Uuid::new_v4()), &request)?
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("Quality measure {} already exists", request.code)))?;
+        let measure = measure_from_row(&row)?;
+
+        tracing::info!("Quality measure {} ({}) configured by {}", measure.code, measure.id, actor);
+        Ok(measure)
+    }
+
+    fn bind_measure<'q>(
+        query: sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>,
+        request: &QualityMeasureRequest,
+    ) -> Result<sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>, HimsError> {
+        let populations = serde_json::to_value(&request.populations).map_err(|e| HimsError::InternalError {
+            message: format!("Failed to serialize measure populations: {}", e),
+        })?;
+        Ok(query
+            .bind(request.code.clone())
+            .bind(request.name.clone())
+            .bind(request.description.clone())
+            .bind(request.url.clone())
+            .bind(request.version_specific_id.clone())
+            .bind(request.improvement_notation.as_str())
+            .bind(request.period_days)
+            .bind(populations)
+            .bind(request.active))
+    }
+
+    /// Replace a measure. `None` if there is no such measure.
+    pub async fn update_measure(
+        &self,
+        id: Uuid,
+        mut request: QualityMeasureRequest,
+        actor: Uuid,
+    ) -> Result<Option<QualityMeasure>, HimsError> {
+        request.check().map_err(validation_error)?;
+        self.require(actor, &[Action::Configure]).await?;
+
+        let row = Self::bind_measure(sqlx::query(UPDATE_MEASURE).bind(id), &request)?
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("Quality measure {} already exists", request.code)))?;
+
+        tracing::info!("Quality measure {} updated by {}", id, actor);
+        row.as_ref().map(measure_from_row).transpose()
+    }
+
+    /// Get a measure
+    pub async fn get_measure(&self, id: Uuid, actor: Uuid) -> Result<Option<QualityMeasure>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        self.find_measure(id).await
+    }
+
+    async fn find_measure(&self, id: Uuid) -> Result<Option<QualityMeasure>, HimsError> {
+        let row = sqlx::query(GET_MEASURE_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        row.as_ref().map(measure_from_row).transpose()
+    }
+
+    /// All measures, by code
+    pub async fn list_measures(&self, actor: Uuid) -> Result<Vec<QualityMeasure>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        let rows = sqlx::query(LIST_MEASURES)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        rows.iter().map(measure_from_row).collect()
+    }
+
+    /// Evaluate a measure over a measurement period ending today, and
+    /// record the result. `None` if there is no such measure.
+    pub async fn evaluate(
+        &self,
+        id: Uuid,
+        request: &EvaluateMeasureRequest,
+        actor: Uuid,
+    ) -> Result<Option<MeasureResult>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        let Some(measure) = self.find_measure(id).await? else {
+            return Ok(None);
+        };
+        if !measure.active {
+            return Err(validation_error(format!("Quality measure {} is not active", measure.code)));
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        if let Some(cohort_id) = request.cohort_id {
+            let exists: bool = sqlx::query_scalar(COHORT_EXISTS)
+                .bind(cohort_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(database_error)?;
+            if !exists {
+                return Err(validation_error(format!("Cohort {} does not exist", cohort_id)));
+            }
+        }
+        sqlx::query(SET_STATEMENT_TIMEOUT)
+            .bind(STATEMENT_TIMEOUT)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+
+        let mut query = QueryBuilder::<Postgres>::new("");
+        measure.populations.push_counts(&mut query, request.cohort_id).map_err(validation_error)?;
+        let row = query.build().fetch_one(&mut *tx).await.map_err(evaluation_error)?;
+        let counts = PopulationCounts {
+            initial_population: row.get("initial_population"),
+            denominator: row.get("denominator"),
+            denominator_exclusion: row.get("denominator_exclusion"),
+            numerator: row.get("numerator"),
+        };
+
+        let period_end = Utc::now().date_naive();
+        let period_start = period_end - Duration::days(measure.period_days as i64 - 1);
+        let row = sqlx::query(INSERT_RESULT)
+            .bind(Uuid::new_v4())
+            .bind(id)
+            .bind(request.cohort_id)
+            .bind(period_start)
+            .bind(period_end)
+            .bind(counts.initial_population)
+            .bind(counts.denominator)
+            .bind(counts.denominator_exclusion)
+            .bind(counts.numerator)
+            .bind(actor)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+
+        let result = result_from_row(&row);
+        tracing::info!(
+            "Quality measure {} evaluated by {}: {} of {} in the numerator, {} excluded",
+            measure.code,
+            actor,
+            counts.numerator,
+            counts.denominator,
+            counts.denominator_exclusion
+        );
+        Ok(Some(result))
+    }
+
+    /// A measure's latest results, newest first
+    pub async fn list_results(&self, id: Uuid, actor: Uuid) -> Result<Vec<MeasureResult>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        let rows = sqlx::query(LIST_RESULTS)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(result_from_row).collect())
+    }
+
+    async fn find_result(&self, id: Uuid) -> Result<Option<(QualityMeasure, MeasureResult)>, HimsError> {
+        let Some(row) = sqlx::query(GET_RESULT_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?
+        else {
+            return Ok(None);
+        };
+        let result = result_from_row(&row);
+        let measure = self.find_measure(result.measure_id).await?.ok_or_else(|| HimsError::InternalError {
+            message: format!("Result {} has no measure", id),
+        })?;
+        Ok(Some((measure, result)))
+    }
+
+    /// A result as a FHIR MeasureReport
+    pub async fn measure_report(&self, id: Uuid, actor: Uuid) -> Result<Option<Value>, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics]).await?;
+        Ok(self.find_result(id).await?.map(|(measure, result)| measure_report(&measure, &result)))
+    }
+
+    /// Results as a QRDA Category III document
+    pub async fn qrda(&self, query: &QrdaQuery, actor: Uuid) -> Result<String, HimsError> {
+        self.require(actor, &[Action::ViewAnalytics, Action::ExportData]).await?;
+        let mut ids: Vec<Uuid> = Vec::new();
+        for id in query.results.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+            let id = Uuid::parse_str(id).map_err(|_| validation_error(format!("{} is not a result ID", id)))?;
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        if ids.len() > MAX_QRDA_RESULTS {
+            return Err(validation_error(format!("A QRDA III report holds at most {} results", MAX_QRDA_RESULTS)));
+        }
+
+        let mut results = Vec::new();
+        for id in ids {
+            let result = self
+                .find_result(id)
+                .await?
+                .ok_or_else(|| validation_error(format!("Result {} does not exist", id)))?;
+            if results.iter().any(|(measure, _): &(QualityMeasure, MeasureResult)| measure.id == result.0.id) {
+                return Err(validation_error(format!("Measure {} is reported once per document", result.0.code)));
+            }
+            results.push(result);
+        }
+        let xml = qrda_category_three(&results, &self.config.organization, Utc::now()).map_err(validation_error)?;
+
+        tracing::info!("User {} exported a QRDA III report of {} measures", actor, results.len());
+        Ok(xml)
+    }
+}
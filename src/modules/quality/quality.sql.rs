@@ -0,0 +1,76 @@
+//! Quality Measure SQL Queries
+//!
+//! This file contains all SQL queries used by the quality measure service.
+//! Queries counting a measure's populations are compiled from its criteria.
+
+/// Insert a quality measure
+pub const INSERT_MEASURE: &str = r#"
+    INSERT INTO quality_measures (
+        id, code, name, description, url, version_specific_id, improvement_notation, period_days, populations, active
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    RETURNING id, code, name, description, url, version_specific_id, improvement_notation, period_days, populations,
+              active, created_at, updated_at
+"#;
+
+/// Get a quality measure by ID
+pub const GET_MEASURE_BY_ID: &str = r#"
+    SELECT id, code, name, description, url, version_specific_id, improvement_notation, period_days, populations,
+           active, created_at, updated_at
+    FROM quality_measures
+    WHERE id = $1
+"#;
+
+/// All quality measures, by code
+pub const LIST_MEASURES: &str = r#"
+    SELECT id, code, name, description, url, version_specific_id, improvement_notation, period_days, populations,
+           active, created_at, updated_at
+    FROM quality_measures
+    ORDER BY code
+"#;
+
+/// Replace a quality measure; its results stand
+pub const UPDATE_MEASURE: &str = r#"
+    UPDATE quality_measures
+    SET code = $2, name = $3, description = $4, url = $5, version_specific_id = $6, improvement_notation = $7,
+        period_days = $8, populations = $9, active = $10
+    WHERE id = $1
+    RETURNING id, code, name, description, url, version_specific_id, improvement_notation, period_days, populations,
+              active, created_at, updated_at
+"#;
+
+/// Whether a cohort is active
+pub const COHORT_EXISTS: &str = r#"
+    SELECT EXISTS (SELECT 1 FROM cohort_definitions WHERE id = $1 AND is_active = true)
+"#;
+
+/// Record a result of a quality measure
+pub const INSERT_RESULT: &str = r#"
+    INSERT INTO quality_measure_results (
+        id, measure_id, cohort_id, period_start, period_end, initial_population, denominator, denominator_exclusion,
+        numerator, evaluated_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    RETURNING id, measure_id, cohort_id, period_start, period_end, initial_population, denominator,
+              denominator_exclusion, numerator, evaluated_by, evaluated_at
+"#;
+
+/// Get a quality measure result by ID
+pub const GET_RESULT_BY_ID: &str = r#"
+    SELECT id, measure_id, cohort_id, period_start, period_end, initial_population, denominator,
+           denominator_exclusion, numerator, evaluated_by, evaluated_at
+    FROM quality_measure_results
+    WHERE id = $1
+"#;
+
+/// A measure's latest results
+pub const LIST_RESULTS: &str = r#"
+    SELECT id, measure_id, cohort_id, period_start, period_end, initial_population, denominator,
+           denominator_exclusion, numerator, evaluated_by, evaluated_at
+    FROM quality_measure_results
+    WHERE measure_id = $1
+    ORDER BY evaluated_at DESC
+    LIMIT 100
+"#;
+
+/// Limit the statement time of an evaluation, for the rest of its
+/// transaction
+pub const SET_STATEMENT_TIMEOUT: &str = "SELECT set_config('statement_timeout', $1, true)";
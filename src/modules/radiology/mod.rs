@@ -0,0 +1,56 @@
+//! Radiology Module
+//!
+//! This module provides radiology order management:
+//! - Imaging orders, served as FHIR ServiceRequests, each assigned an
+//!   accession number and study instance UID
+//! - Scheduling orders on modalities, and the DICOM modality worklist of
+//!   scheduled orders as DICOM JSON
+//! - Linking DICOM instances received from modalities to their orders and
+//!   encounters, by study instance UID or accession number
+//! - Reports written on performed studies as diagnostic report medical
+//!   records, signed off and served as FHIR DiagnosticReports
+
+#[path = "radiology.controller.rs"]
+pub mod radiology_controller;
+#[path = "radiology.service.rs"]
+pub mod radiology_service;
+#[path = "radiology.sql.rs"]
+pub mod radiology_sql;
+#[path = "radiology.worklist.rs"]
+pub mod radiology_worklist;
+
+pub use radiology_controller::RadiologyController;
+pub use radiology_service::{ImagingOrder, RadiologyService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::medical_record::MedicalRecordService;
+use crate::modules::patient::PatientService;
+use crate::utils::api_router::ApiRouter;
+
+/// Radiology Module Configuration
+pub struct RadiologyModule {
+    pub service: Arc<RadiologyService>,
+    pub controller: Arc<RadiologyController>,
+}
+
+impl RadiologyModule {
+    /// Create a new Radiology Module with dependency injection
+    pub fn new(db_pool: PgPool, patients: Arc<PatientService>, medical_records: Arc<MedicalRecordService>) -> Self {
+        let service = Arc::new(RadiologyService::new(db_pool, patients, medical_records));
+        let controller = Arc::new(RadiologyController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<RadiologyService> {
+        self.service.clone()
+    }
+}
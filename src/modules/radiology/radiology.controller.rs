@@ -0,0 +1,451 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{
+    DIAGNOSTIC_REPORT_RESOURCE_TYPE, DIAGNOSTIC_SERVICE_SECTION_SYSTEM, DICOM_UID_SYSTEM, IMAGING_CATEGORY_CODE,
+    IMAGING_ORDER_STATUS_EXTENSION, SERVICE_REQUEST_RESOURCE_TYPE, SNOMED_CT_SYSTEM,
+};
+use crate::models::{CodeableConcept, Coding, DocumentStatus, Identifier, MedicalRecord, Reference, ResourceMeta};
+use crate::modules::radiology::radiology_service::{
+    ImagingOrder, ImagingOrderRequest, ImagingOrderStatus, ReportRequest, ScheduleRequest, WorklistQuery,
+};
+use crate::modules::radiology::RadiologyService;
+use crate::modules::referral::referral_controller::{
+    Annotation, DisplayReference, ServiceRequestExtension, ServiceRequestResponse,
+};
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Largest DICOM instance accepted, in bytes
+const MAX_INSTANCE_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct OrderListQuery {
+    pub patient_id: Option<Uuid>,
+    pub status: Option<String>,
+}
+
+/// Filters of the modality worklist, as modalities and brokers query it
+#[derive(Debug, Default, Deserialize)]
+pub struct WorklistParams {
+    pub date: Option<NaiveDate>,
+    pub modality: Option<String>,
+    pub station: Option<String>,
+}
+
+/// The encounter to link a received study to, when its order has none
+#[derive(Debug, Default, Deserialize)]
+pub struct StudyQuery {
+    pub encounter_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelRequest {
+    pub reason: String,
+}
+
+/// FHIR R4 DiagnosticReport as a radiology report is served
+#[derive(Debug, Serialize)]
+pub struct DiagnosticReportResponse {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    /// The order's accession number and study instance UID
+    pub identifier: Vec<Identifier>,
+    #[serde(rename = "basedOn")]
+    pub based_on: Vec<Reference>,
+    pub status: String,
+    pub category: Vec<CodeableConcept>,
+    pub code: CodeableConcept,
+    pub subject: Reference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "effectiveDateTime")]
+    pub effective_date_time: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<Reference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(rename = "resultsInterpreter")]
+    pub results_interpreter: Vec<Reference>,
+    pub conclusion: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Radiology controller for imaging orders, the modality worklist, DICOM
+/// studies and reports
+pub struct RadiologyController {
+    radiology_service: Arc<RadiologyService>,
+}
+
+impl RadiologyController {
+    /// Create new controller with injected service
+    pub fn new(radiology_service: Arc<RadiologyService>) -> Self {
+        Self { radiology_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/orders", Self::create_order, "Place an imaging order")
+            .get("/orders", Self::list_orders, "List imaging orders")
+            .get("/orders/:id", Self::get_order, "Get imaging order by ID")
+            .get("/orders/:id/service-request", Self::service_request, "Get an imaging order as a FHIR ServiceRequest")
+            .put("/orders/:id/schedule", Self::schedule_order, "Schedule an imaging order on a modality")
+            .post("/orders/:id/cancel", Self::cancel_order, "Cancel an imaging order")
+            .post("/orders/:id/report", Self::write_report, "Write the report on an imaging order's study")
+            .post("/orders/:id/report/sign", Self::sign_report, "Sign off an imaging order's report")
+            .get("/orders/:id/diagnostic-report", Self::diagnostic_report, "Get an imaging order's report as a FHIR DiagnosticReport")
+            .get("/worklist", Self::worklist, "Get the DICOM modality worklist")
+            .post("/studies", Self::receive_study, "Link a DICOM instance to its imaging order")
+            .with_state(self.radiology_service.clone())
+    }
+
+    pub async fn create_order(
+        State(service): State<Arc<RadiologyService>>,
+        headers: HeaderMap,
+        Json(request): Json<ImagingOrderRequest>,
+    ) -> Result<(StatusCode, Json<ImagingOrder>), ErrorReply> {
+        tracing::info!("Placing {} order for patient {}", request.modality, request.patient_id);
+
+        match service.create_order(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(order) => Ok((StatusCode::CREATED, Json(order))),
+            Err(e) => Err(Self::error_response("Failed to place imaging order", e)),
+        }
+    }
+
+    pub async fn list_orders(
+        State(service): State<Arc<RadiologyService>>,
+        Query(query): Query<OrderListQuery>,
+    ) -> Result<Json<Vec<ImagingOrder>>, ErrorReply> {
+        let status = match query.status.as_deref() {
+            Some(status) => Some(ImagingOrderStatus::from_string(status).ok_or_else(|| {
+                Self::error_response(
+                    "Invalid status",
+                    HimsError::ValidationError {
+                        message: format!("Unknown imaging order status: {}", status),
+                    },
+                )
+            })?),
+            None => None,
+        };
+
+        match service.list_orders(query.patient_id, status).await {
+            Ok(orders) => Ok(Json(orders)),
+            Err(e) => Err(Self::error_response("Failed to list imaging orders", e)),
+        }
+    }
+
+    pub async fn get_order(
+        State(service): State<Arc<RadiologyService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<ImagingOrder>, ErrorReply> {
+        match service.get_order(id).await {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Imaging order", id)),
+            Err(e) => Err(Self::error_response("Failed to get imaging order", e)),
+        }
+    }
+
+    pub async fn service_request(
+        State(service): State<Arc<RadiologyService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<ServiceRequestResponse>, ErrorReply> {
+        match service.get_order(id).await {
+            Ok(Some(order)) => Ok(Json(Self::order_to_service_request(order))),
+            Ok(None) => Err(Self::not_found("Imaging order", id)),
+            Err(e) => Err(Self::error_response("Failed to get imaging order", e)),
+        }
+    }
+
+    pub async fn schedule_order(
+        State(service): State<Arc<RadiologyService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ScheduleRequest>,
+    ) -> Result<Json<ImagingOrder>, ErrorReply> {
+        tracing::info!("Scheduling imaging order {} at {}", id, request.scheduled_at);
+
+        match service.schedule_order(id, request).await {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Imaging order", id)),
+            Err(e) => Err(Self::error_response("Failed to schedule imaging order", e)),
+        }
+    }
+
+    pub async fn cancel_order(
+        State(service): State<Arc<RadiologyService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<CancelRequest>,
+    ) -> Result<Json<ImagingOrder>, ErrorReply> {
+        tracing::info!("Cancelling imaging order {}", id);
+
+        match service.cancel_order(id, request.reason).await {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Imaging order", id)),
+            Err(e) => Err(Self::error_response("Failed to cancel imaging order", e)),
+        }
+    }
+
+    /// Scheduled orders as DICOM JSON worklist items, for a modality or a
+    /// worklist broker in front of it
+    pub async fn worklist(
+        State(service): State<Arc<RadiologyService>>,
+        Query(params): Query<WorklistParams>,
+    ) -> Result<([(header::HeaderName, &'static str); 1], Json<Vec<serde_json::Value>>), ErrorReply> {
+        let query = WorklistQuery {
+            date: params.date,
+            modality: params.modality,
+            station_ae_title: params.station,
+        };
+
+        match service.worklist(&query).await {
+            Ok(items) => Ok(([(header::CONTENT_TYPE, "application/dicom+json")], Json(items))),
+            Err(e) => Err(Self::error_response("Failed to get worklist", e)),
+        }
+    }
+
+    /// Link a DICOM Part 10 instance in the request body to its order; each
+    /// instance of a study may be sent, adding its series to the order
+    pub async fn receive_study(
+        State(service): State<Arc<RadiologyService>>,
+        Query(query): Query<StudyQuery>,
+        body: Body,
+    ) -> Result<Json<ImagingOrder>, ErrorReply> {
+        let bytes = to_bytes(body, MAX_INSTANCE_BYTES).await.map_err(|_| {
+            (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse {
+                    error: "Instance too large".to_string(),
+                    message: format!("DICOM instances may be at most {} bytes", MAX_INSTANCE_BYTES),
+                }),
+            )
+        })?;
+        tracing::info!("Received DICOM instance of {} bytes", bytes.len());
+
+        match service.link_study(&bytes, query.encounter_id).await {
+            Ok(order) => Ok(Json(order)),
+            Err(e) => Err(Self::error_response("Failed to link study", e)),
+        }
+    }
+
+    pub async fn write_report(
+        State(service): State<Arc<RadiologyService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ReportRequest>,
+    ) -> Result<(StatusCode, Json<ImagingOrder>), ErrorReply> {
+        tracing::info!("Writing report on imaging order {}", id);
+
+        match service.write_report(id, request, extract_user_from_headers(&headers).ok()).await {
+            Ok(Some(order)) => Ok((StatusCode::CREATED, Json(order))),
+            Ok(None) => Err(Self::not_found("Imaging order", id)),
+            Err(e) => Err(Self::error_response("Failed to write report", e)),
+        }
+    }
+
+    /// Sign off a report; the caller is recorded as its interpreter
+    pub async fn sign_report(
+        State(service): State<Arc<RadiologyService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<ImagingOrder>, ErrorReply> {
+        tracing::info!("Signing off report on imaging order {}", id);
+
+        match service.sign_report(id, extract_user_from_headers(&headers).ok()).await {
+            Ok(Some(order)) => Ok(Json(order)),
+            Ok(None) => Err(Self::not_found("Imaging order", id)),
+            Err(e) => Err(Self::error_response("Failed to sign off report", e)),
+        }
+    }
+
+    pub async fn diagnostic_report(
+        State(service): State<Arc<RadiologyService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<DiagnosticReportResponse>, ErrorReply> {
+        match service.get_report(id).await {
+            Ok(Some((order, Some(record)))) => Ok(Json(Self::report_to_diagnostic_report(order, record))),
+            Ok(Some((order, None))) => Err(Self::not_found("Report of imaging order", order.id)),
+            Ok(None) => Err(Self::not_found("Imaging order", id)),
+            Err(e) => Err(Self::error_response("Failed to get report", e)),
+        }
+    }
+
+    /// The order's accession number and study instance UID as identifiers
+    fn order_identifiers(order: &ImagingOrder) -> Vec<Identifier> {
+        vec![
+            Identifier {
+                use_type: Some("usual".to_string()),
+                system: None,
+                value: order.accession_number.clone(),
+            },
+            Identifier {
+                use_type: Some("official".to_string()),
+                system: Some(DICOM_UID_SYSTEM.to_string()),
+                value: format!("urn:oid:{}", order.study_instance_uid),
+            },
+        ]
+    }
+
+    /// Convert an imaging order to a FHIR ServiceRequest
+    pub fn order_to_service_request(order: ImagingOrder) -> ServiceRequestResponse {
+        let requester = (order.requester_id.is_some() || order.requester_display.is_some()).then(|| DisplayReference {
+            reference: order.requester_id.map(|id| format!("Practitioner/{}", id)),
+            display: order.requester_display.clone(),
+        });
+        let performer = order.station_ae_title.clone().map(|station| DisplayReference {
+            reference: None,
+            display: Some(station),
+        });
+        let mut note: Vec<Annotation> = order.note.clone().into_iter().map(|text| Annotation { text }).collect();
+        note.extend(order.cancellation_reason.clone().map(|text| Annotation { text }));
+
+        ServiceRequestResponse {
+            resource_type: SERVICE_REQUEST_RESOURCE_TYPE.to_string(),
+            id: order.id,
+            meta: ResourceMeta {
+                version_id: None,
+                last_updated: order.updated_at,
+                profile: vec![],
+                security: vec![],
+                tag: vec![],
+            },
+            extension: vec![ServiceRequestExtension {
+                url: IMAGING_ORDER_STATUS_EXTENSION.to_string(),
+                value_code: Some(order.status.as_str().to_string()),
+                value_reference: None,
+            }],
+            identifier: Self::order_identifiers(&order),
+            status: order.status.request_status().to_string(),
+            intent: "order".to_string(),
+            category: vec![CodeableConcept {
+                coding: vec![Coding {
+                    system: Some(SNOMED_CT_SYSTEM.to_string()),
+                    version: None,
+                    code: Some(IMAGING_CATEGORY_CODE.to_string()),
+                    display: Some("Imaging".to_string()),
+                }],
+                text: None,
+            }],
+            priority: order.priority.as_str().to_string(),
+            code: Some(order.code),
+            subject: Reference {
+                reference: format!("Patient/{}", order.patient_id),
+                display: None,
+            },
+            authored_on: order.created_at,
+            requester,
+            performer_type: vec![CodeableConcept {
+                coding: vec![],
+                text: Some(order.modality),
+            }],
+            performer: performer.into_iter().collect(),
+            reason_code: order
+                .reason
+                .map(|text| CodeableConcept {
+                    coding: vec![],
+                    text: Some(text),
+                })
+                .into_iter()
+                .collect(),
+            reason_reference: vec![],
+            note,
+        }
+    }
+
+    /// Convert an order and its report's medical record to a FHIR
+    /// DiagnosticReport, final once the report is signed off
+    pub fn report_to_diagnostic_report(order: ImagingOrder, record: MedicalRecord) -> DiagnosticReportResponse {
+        let status = match (&order.status, &record.status) {
+            (_, DocumentStatus::EnteredInError) => "entered-in-error",
+            (_, DocumentStatus::Amended) => "amended",
+            (ImagingOrderStatus::Reported, _) => "final",
+            _ => "preliminary",
+        };
+
+        DiagnosticReportResponse {
+            resource_type: DIAGNOSTIC_REPORT_RESOURCE_TYPE.to_string(),
+            id: record.id,
+            meta: record.meta,
+            identifier: Self::order_identifiers(&order),
+            based_on: vec![Reference {
+                reference: format!("ServiceRequest/{}", order.id),
+                display: None,
+            }],
+            status: status.to_string(),
+            category: vec![CodeableConcept {
+                coding: vec![Coding {
+                    system: Some(DIAGNOSTIC_SERVICE_SECTION_SYSTEM.to_string()),
+                    version: None,
+                    code: Some("RAD".to_string()),
+                    display: Some("Radiology".to_string()),
+                }],
+                text: None,
+            }],
+            code: order.code,
+            subject: record.subject,
+            encounter: order.encounter_id.map(|id| Reference {
+                reference: format!("Encounter/{}", id),
+                display: None,
+            }),
+            effective_date_time: order.performed_at,
+            issued: order.reported_at,
+            performer: record.author,
+            results_interpreter: order
+                .reported_by
+                .map(|id| Reference {
+                    reference: format!("Practitioner/{}", id),
+                    display: None,
+                })
+                .into_iter()
+                .collect(),
+            conclusion: record.content,
+        }
+    }
+
+    fn not_found(kind: &str, id: Uuid) -> ErrorReply {
+        tracing::warn!("{} not found: {}", kind, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", kind),
+                message: format!("{} with id {} not found", kind, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
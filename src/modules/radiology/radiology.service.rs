@@ -0,0 +1,746 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{CodeableConcept, MedicalRecord, MedicalRecordType, Patient, Reference, TaskPriority};
+use crate::modules::auth::AuthContext;
+use crate::modules::medical_record::medical_record_controller::MedicalRecordCreateRequest;
+use crate::modules::medical_record::MedicalRecordService;
+use crate::modules::patient::PatientService;
+use crate::modules::radiology::radiology_worklist::worklist_item;
+use crate::standards::dicom::{generate_uid, DicomInstance, DicomParser};
+
+// Import SQL queries from separate file
+use crate::modules::radiology::radiology_sql::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImagingOrderStatus {
+    Ordered,
+    Scheduled,
+    Performed,
+    Reported,
+    Cancelled,
+}
+
+impl ImagingOrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImagingOrderStatus::Ordered => "ordered",
+            ImagingOrderStatus::Scheduled => "scheduled",
+            ImagingOrderStatus::Performed => "performed",
+            ImagingOrderStatus::Reported => "reported",
+            ImagingOrderStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "ordered" => Some(ImagingOrderStatus::Ordered),
+            "scheduled" => Some(ImagingOrderStatus::Scheduled),
+            "performed" => Some(ImagingOrderStatus::Performed),
+            "reported" => Some(ImagingOrderStatus::Reported),
+            "cancelled" => Some(ImagingOrderStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// FHIR request-status of the ServiceRequest the order is served as
+    pub fn request_status(&self) -> &'static str {
+        match self {
+            ImagingOrderStatus::Ordered | ImagingOrderStatus::Scheduled | ImagingOrderStatus::Performed => "active",
+            ImagingOrderStatus::Reported => "completed",
+            ImagingOrderStatus::Cancelled => "revoked",
+        }
+    }
+}
+
+/// The DICOM study acquired for an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkedStudy {
+    pub study_instance_uid: String,
+    /// Series received so far
+    pub series_instance_uids: Vec<String>,
+    pub modality: Option<String>,
+    /// Study date and time, in the modality's local time
+    pub started_at: Option<NaiveDateTime>,
+    pub description: Option<String>,
+}
+
+/// An imaging order
+#[derive(Debug, Clone, Serialize)]
+pub struct ImagingOrder {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub status: ImagingOrderStatus,
+    pub priority: TaskPriority,
+    /// The imaging procedure, e.g. a LOINC or SNOMED CT code
+    pub code: CodeableConcept,
+    /// DICOM modality, e.g. CT
+    pub modality: String,
+    pub body_site: Option<String>,
+    pub reason: Option<String>,
+    pub requester_id: Option<Uuid>,
+    pub requester_display: Option<String>,
+    pub accession_number: String,
+    pub study_instance_uid: String,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    /// AE title of the modality the order is scheduled on
+    pub station_ae_title: Option<String>,
+    pub study: Option<LinkedStudy>,
+    pub performed_at: Option<DateTime<Utc>>,
+    /// The diagnostic report medical record
+    pub report_id: Option<Uuid>,
+    /// When and by whom the report was signed off
+    pub reported_at: Option<DateTime<Utc>>,
+    pub reported_by: Option<Uuid>,
+    pub cancellation_reason: Option<String>,
+    pub note: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn order_from_row(row: &PgRow) -> ImagingOrder {
+    ImagingOrder {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        status: ImagingOrderStatus::from_string(row.get("status")).unwrap_or(ImagingOrderStatus::Ordered),
+        priority: TaskPriority::from_string(row.get("priority")),
+        code: serde_json::from_value(row.get("code")).unwrap_or(CodeableConcept {
+            coding: Vec::new(),
+            text: None,
+        }),
+        modality: row.get("modality"),
+        body_site: row.get("body_site"),
+        reason: row.get("reason"),
+        requester_id: row.get("requester_id"),
+        requester_display: row.get("requester_display"),
+        accession_number: row.get("accession_number"),
+        study_instance_uid: row.get("study_instance_uid"),
+        scheduled_at: row.get("scheduled_at"),
+        station_ae_title: row.get("station_ae_title"),
+        study: row
+            .get::<Option<serde_json::Value>, _>("study")
+            .and_then(|study| serde_json::from_value(study).ok()),
+        performed_at: row.get("performed_at"),
+        report_id: row.get("report_id"),
+        reported_at: row.get("reported_at"),
+        reported_by: row.get("reported_by"),
+        cancellation_reason: row.get("cancellation_reason"),
+        note: row.get("note"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// Check a DICOM code string: 1 to 16 upper-case letters, digits, spaces
+/// or underscores
+fn check_code_string(name: &str, value: &str) -> Result<(), String> {
+    let valid = !value.is_empty()
+        && value.len() <= 16
+        && value.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == ' ' || c == '_');
+    match valid {
+        true => Ok(()),
+        false => Err(format!("{} {:?} is not 1 to 16 upper-case letters, digits or underscores", name, value)),
+    }
+}
+
+/// Check an application entity title: 1 to 16 printable ASCII characters
+/// other than `\`
+fn check_ae_title(title: &str) -> Result<(), String> {
+    let valid = !title.is_empty()
+        && title.len() <= 16
+        && title.chars().all(|c| (c.is_ascii_graphic() && c != '\\') || c == ' ');
+    match valid {
+        true => Ok(()),
+        false => Err(format!("AE title {:?} is not 1 to 16 printable ASCII characters", title)),
+    }
+}
+
+impl ImagingOrder {
+    /// Schedule an order on a modality, or move it
+    pub fn schedule(&mut self, at: DateTime<Utc>, station_ae_title: Option<String>) -> Result<(), String> {
+        if !matches!(self.status, ImagingOrderStatus::Ordered | ImagingOrderStatus::Scheduled) {
+            return Err(format!("Order {} is {} and cannot be scheduled", self.id, self.status.as_str()));
+        }
+        self.status = ImagingOrderStatus::Scheduled;
+        self.scheduled_at = Some(at);
+        self.station_ae_title = station_ae_title;
+        Ok(())
+    }
+
+    /// Cancel an order that has not been performed
+    pub fn cancel(&mut self, reason: String) -> Result<(), String> {
+        if !matches!(self.status, ImagingOrderStatus::Ordered | ImagingOrderStatus::Scheduled) {
+            return Err(format!("Order {} is {} and cannot be cancelled", self.id, self.status.as_str()));
+        }
+        self.status = ImagingOrderStatus::Cancelled;
+        self.cancellation_reason = Some(reason);
+        Ok(())
+    }
+
+    /// Link an instance of the study acquired for the order. The first
+    /// instance marks the order performed; the study's later instances add
+    /// their series. An instance of another study is refused.
+    pub fn link_instance(&mut self, instance: &DicomInstance, now: DateTime<Utc>) -> Result<(), String> {
+        if self.status == ImagingOrderStatus::Cancelled {
+            return Err(format!("Order {} is cancelled", self.id));
+        }
+        let study = self.study.get_or_insert_with(|| LinkedStudy {
+            study_instance_uid: instance.study_instance_uid.clone(),
+            series_instance_uids: Vec::new(),
+            modality: instance.modality.clone(),
+            started_at: instance.study_date_time,
+            description: instance.study_description.clone(),
+        });
+        if study.study_instance_uid != instance.study_instance_uid {
+            return Err(format!(
+                "Order {} already has study {}, not {}",
+                self.id, study.study_instance_uid, instance.study_instance_uid
+            ));
+        }
+        if let Some(series) = &instance.series_instance_uid {
+            if !study.series_instance_uids.contains(series) {
+                study.series_instance_uids.push(series.clone());
+            }
+        }
+        // A modality that generated its own study UID is matched by
+        // accession number; the order takes the study's UID
+        self.study_instance_uid = instance.study_instance_uid.clone();
+        if matches!(self.status, ImagingOrderStatus::Ordered | ImagingOrderStatus::Scheduled) {
+            self.status = ImagingOrderStatus::Performed;
+            self.performed_at = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Record the sign-off of the order's report
+    pub fn sign_off(&mut self, now: DateTime<Utc>, signed_by: Option<Uuid>) -> Result<(), String> {
+        if self.status != ImagingOrderStatus::Performed {
+            return Err(format!("Order {} is {}; reports are signed off on performed orders", self.id, self.status.as_str()));
+        }
+        if self.report_id.is_none() {
+            return Err(format!("Order {} has no report to sign off", self.id));
+        }
+        self.status = ImagingOrderStatus::Reported;
+        self.reported_at = Some(now);
+        self.reported_by = signed_by;
+        Ok(())
+    }
+}
+
+/// An imaging order as clients send it
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImagingOrderRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    pub code: CodeableConcept,
+    pub modality: String,
+    pub body_site: Option<String>,
+    pub reason: Option<String>,
+    /// Who ordered the study, as the worklist names them
+    pub requester_display: Option<String>,
+    pub note: Option<String>,
+}
+
+/// When and on which modality an order is scheduled
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleRequest {
+    pub scheduled_at: DateTime<Utc>,
+    pub station_ae_title: Option<String>,
+}
+
+/// The report on an order's study, written as a diagnostic report medical
+/// record of the order's patient and encounter
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportRequest {
+    /// Rendered from `structured_data` when empty and a template is named
+    #[serde(default)]
+    pub content: String,
+    pub author: Reference,
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub structured_data: Option<serde_json::Value>,
+}
+
+/// Which scheduled orders the worklist lists
+#[derive(Debug, Clone, Default)]
+pub struct WorklistQuery {
+    /// Orders scheduled on this day, in UTC
+    pub date: Option<NaiveDate>,
+    pub modality: Option<String>,
+    pub station_ae_title: Option<String>,
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Radiology service for imaging orders, the modality worklist, DICOM
+/// studies and reports
+pub struct RadiologyService {
+    pool: PgPool,
+    patients: Arc<PatientService>,
+    medical_records: Arc<MedicalRecordService>,
+}
+
+impl RadiologyService {
+    /// Create new radiology service
+    pub fn new(pool: PgPool, patients: Arc<PatientService>, medical_records: Arc<MedicalRecordService>) -> Self {
+        Self {
+            pool,
+            patients,
+            medical_records,
+        }
+    }
+
+    async fn patient(&self, id: Uuid) -> Result<Patient, HimsError> {
+        self.patients
+            .get_patient(id)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| HimsError::ValidationError {
+                message: format!("Patient {} does not exist", id),
+            })
+    }
+
+    /// Check an encounter an order is linked to is of the order's patient
+    async fn check_encounter(conn: &mut PgConnection, encounter_id: Uuid, patient_id: Uuid) -> Result<(), HimsError> {
+        let subject: Option<Uuid> = sqlx::query(GET_ENCOUNTER_SUBJECT)
+            .bind(encounter_id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?
+            .map(|row| row.get("subject"));
+        match subject {
+            Some(subject) if subject == patient_id => Ok(()),
+            Some(_) => Err(HimsError::ValidationError {
+                message: format!("Encounter {} is not of patient {}", encounter_id, patient_id),
+            }),
+            None => Err(HimsError::ValidationError {
+                message: format!("Encounter {} does not exist", encounter_id),
+            }),
+        }
+    }
+
+    /// Place an imaging order, assigning its accession number and study
+    /// instance UID
+    pub async fn create_order(
+        &self,
+        request: ImagingOrderRequest,
+        requester_id: Option<Uuid>,
+    ) -> Result<ImagingOrder, HimsError> {
+        check_code_string("Modality", &request.modality).map_err(|message| HimsError::ValidationError { message })?;
+        if request.code.coding.is_empty() && request.code.text.is_none() {
+            return Err(HimsError::ValidationError {
+                message: "An imaging order needs a procedure".to_string(),
+            });
+        }
+        self.patient(request.patient_id).await?;
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        if let Some(encounter_id) = request.encounter_id {
+            Self::check_encounter(&mut conn, encounter_id, request.patient_id).await?;
+        }
+
+        let row = sqlx::query(INSERT_ORDER)
+            .bind(Uuid::new_v4())
+            .bind(request.patient_id)
+            .bind(request.encounter_id)
+            .bind(request.priority.as_str())
+            .bind(serde_json::to_value(&request.code).unwrap_or_default())
+            .bind(&request.modality)
+            .bind(&request.body_site)
+            .bind(&request.reason)
+            .bind(requester_id)
+            .bind(&request.requester_display)
+            .bind(generate_uid())
+            .bind(&request.note)
+            .bind(requester_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(database_error)?;
+        Ok(order_from_row(&row))
+    }
+
+    /// Get imaging order by ID
+    pub async fn get_order(&self, id: Uuid) -> Result<Option<ImagingOrder>, HimsError> {
+        let row = sqlx::query(GET_ORDER_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(order_from_row))
+    }
+
+    /// The 500 newest orders, optionally of one patient or in one status
+    pub async fn list_orders(
+        &self,
+        patient_id: Option<Uuid>,
+        status: Option<ImagingOrderStatus>,
+    ) -> Result<Vec<ImagingOrder>, HimsError> {
+        let rows = sqlx::query(LIST_ORDERS)
+            .bind(patient_id)
+            .bind(status.map(|status| status.as_str()))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(order_from_row).collect())
+    }
+
+    async fn lock_order(conn: &mut PgConnection, id: Uuid) -> Result<Option<ImagingOrder>, HimsError> {
+        let row = sqlx::query(LOCK_ORDER)
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(order_from_row))
+    }
+
+    async fn save(conn: &mut PgConnection, order: &ImagingOrder) -> Result<(), HimsError> {
+        sqlx::query(UPDATE_ORDER_PROGRESS)
+            .bind(order.id)
+            .bind(order.status.as_str())
+            .bind(order.encounter_id)
+            .bind(&order.study_instance_uid)
+            .bind(order.scheduled_at)
+            .bind(&order.station_ae_title)
+            .bind(order.study.as_ref().map(|study| serde_json::to_value(study).unwrap_or_default()))
+            .bind(order.performed_at)
+            .bind(order.report_id)
+            .bind(order.reported_at)
+            .bind(order.reported_by)
+            .bind(&order.cancellation_reason)
+            .execute(conn)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError {
+                    message: format!("Study {} is linked to another order", order.study_instance_uid),
+                },
+                _ => database_error(e),
+            })?;
+        Ok(())
+    }
+
+    /// Move an order on with `step`, or `None` if there is no such order
+    async fn progress(
+        &self,
+        id: Uuid,
+        step: impl FnOnce(&mut ImagingOrder) -> Result<(), String>,
+    ) -> Result<Option<ImagingOrder>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut order) = Self::lock_order(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        step(&mut order).map_err(|message| HimsError::ConflictError { message })?;
+        Self::save(&mut tx, &order).await?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_order(id).await
+    }
+
+    /// Schedule an order on a modality, putting it on the worklist
+    pub async fn schedule_order(&self, id: Uuid, request: ScheduleRequest) -> Result<Option<ImagingOrder>, HimsError> {
+        let station = request.station_ae_title.map(|station| station.trim().to_string());
+        if let Some(station) = &station {
+            check_ae_title(station).map_err(|message| HimsError::ValidationError { message })?;
+        }
+        self.progress(id, |order| order.schedule(request.scheduled_at, station)).await
+    }
+
+    /// Cancel an order that has not been performed
+    pub async fn cancel_order(&self, id: Uuid, reason: String) -> Result<Option<ImagingOrder>, HimsError> {
+        if reason.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Give the reason the order is cancelled".to_string(),
+            });
+        }
+        self.progress(id, |order| order.cancel(reason)).await
+    }
+
+    /// The modality worklist: scheduled orders as DICOM JSON datasets, for
+    /// modalities to query through a worklist broker
+    pub async fn worklist(&self, query: &WorklistQuery) -> Result<Vec<serde_json::Value>, HimsError> {
+        let (from, to) = match query.date {
+            Some(date) => {
+                let from = date.and_time(chrono::NaiveTime::MIN).and_utc();
+                (Some(from), Some(from + chrono::Duration::days(1)))
+            }
+            None => (None, None),
+        };
+        let rows = sqlx::query(GET_WORKLIST)
+            .bind(from)
+            .bind(to)
+            .bind(query.modality.as_deref())
+            .bind(query.station_ae_title.as_deref())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        let mut worklist = Vec::with_capacity(rows.len());
+        for order in rows.iter().map(order_from_row) {
+            let patient = self.patient(order.patient_id).await?;
+            worklist.push(worklist_item(&order, &patient));
+        }
+        Ok(worklist)
+    }
+
+    /// Link a DICOM instance to the order it was acquired for, matched by
+    /// study instance UID or else accession number, and to the encounter
+    /// given when the order has none. The instance's patient ID must be
+    /// the order's patient or one of their identifiers.
+    pub async fn link_study(&self, bytes: &[u8], encounter_id: Option<Uuid>) -> Result<ImagingOrder, HimsError> {
+        let instance = DicomInstance::from_dataset(&DicomParser::parse(bytes)?)?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let row = sqlx::query(LOCK_ORDER_FOR_STUDY)
+            .bind(&instance.study_instance_uid)
+            .bind(instance.accession_number.as_deref())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        let Some(mut order) = row.as_ref().map(order_from_row) else {
+            return Err(HimsError::ValidationError {
+                message: format!(
+                    "No imaging order has study {} or accession number {}",
+                    instance.study_instance_uid,
+                    instance.accession_number.as_deref().unwrap_or("(none)")
+                ),
+            });
+        };
+
+        let patient = self.patient(order.patient_id).await?;
+        let patient_id = instance.patient_id.as_deref().unwrap_or_default();
+        let known = patient_id == patient.id.to_string()
+            || patient.identifier.iter().any(|identifier| identifier.value == patient_id);
+        if !known {
+            return Err(HimsError::ValidationError {
+                message: format!(
+                    "Study {} is of patient ID {:?}, not the patient of order {}",
+                    instance.study_instance_uid, patient_id, order.id
+                ),
+            });
+        }
+
+        match (order.encounter_id, encounter_id) {
+            (Some(linked), Some(given)) if linked != given => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Order {} is of encounter {}, not {}", order.id, linked, given),
+                })
+            }
+            (None, Some(given)) => {
+                Self::check_encounter(&mut tx, given, order.patient_id).await?;
+                order.encounter_id = Some(given);
+            }
+            _ => {}
+        }
+
+        order
+            .link_instance(&instance, Utc::now())
+            .map_err(|message| HimsError::ConflictError { message })?;
+        Self::save(&mut tx, &order).await?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(order)
+    }
+
+    /// Write the report on a performed order's study, as a diagnostic
+    /// report medical record to be signed off. `None` if there is no such
+    /// order.
+    pub async fn write_report(
+        &self,
+        id: Uuid,
+        request: ReportRequest,
+        author_id: Option<Uuid>,
+    ) -> Result<Option<ImagingOrder>, HimsError> {
+        let Some(order) = self.get_order(id).await? else {
+            return Ok(None);
+        };
+        if order.status != ImagingOrderStatus::Performed {
+            return Err(HimsError::ConflictError {
+                message: format!("Order {} is {}; reports are written on performed orders", id, order.status.as_str()),
+            });
+        }
+        if let Some(report_id) = order.report_id {
+            return Err(HimsError::ConflictError {
+                message: format!("Order {} already has report {}", id, report_id),
+            });
+        }
+
+        let record = MedicalRecordCreateRequest {
+            patient_id: order.patient_id,
+            encounter_id: order.encounter_id,
+            record_type: MedicalRecordType::DiagnosticReport,
+            content: request.content,
+            author: request.author,
+            template_id: request.template_id,
+            structured_data: request.structured_data,
+        };
+        let author = author_id.map(AuthContext::new);
+        let record_id = self.medical_records.create_record_from_request(&record, author.as_ref()).await?;
+        let record_id = Uuid::parse_str(&record_id).map_err(|e| HimsError::InternalError {
+            message: format!("Medical record ID {} is not a UUID: {}", record_id, e),
+        })?;
+
+        let rows_affected = sqlx::query(UPDATE_ORDER_REPORT)
+            .bind(id)
+            .bind(record_id)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Order {} was given a report concurrently", id),
+            });
+        }
+        self.get_order(id).await
+    }
+
+    /// Sign off an order's report, finalizing its medical record and
+    /// completing the order. `None` if there is no such order.
+    pub async fn sign_report(&self, id: Uuid, signed_by: Option<Uuid>) -> Result<Option<ImagingOrder>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut order) = Self::lock_order(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        order
+            .sign_off(Utc::now(), signed_by)
+            .map_err(|message| HimsError::ConflictError { message })?;
+
+        let report_id = order.report_id.unwrap_or_default();
+        let record = self
+            .medical_records
+            .get_record_by_uuid(report_id)
+            .await?
+            .ok_or_else(|| HimsError::InternalError {
+                message: format!("Report {} of order {} does not exist", report_id, id),
+            })?;
+        let version = record.meta.version_id.unwrap_or_else(|| "1".to_string());
+        self.medical_records.finalize_record_by_uuid(report_id, &version, None).await?;
+
+        Self::save(&mut tx, &order).await?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(Some(order))
+    }
+
+    /// An order with its report's medical record, if it has one. `None` if
+    /// there is no such order.
+    pub async fn get_report(&self, id: Uuid) -> Result<Option<(ImagingOrder, Option<MedicalRecord>)>, HimsError> {
+        let Some(order) = self.get_order(id).await? else {
+            return Ok(None);
+        };
+        let record = match order.report_id {
+            Some(report_id) => self.medical_records.get_record_by_uuid(report_id).await?,
+            None => None,
+        };
+        Ok(Some((order, record)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order() -> ImagingOrder {
+        let now = Utc::now();
+        ImagingOrder {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            encounter_id: None,
+            status: ImagingOrderStatus::Ordered,
+            priority: TaskPriority::Routine,
+            code: CodeableConcept {
+                coding: Vec::new(),
+                text: Some("CT head without contrast".to_string()),
+            },
+            modality: "CT".to_string(),
+            body_site: None,
+            reason: None,
+            requester_id: None,
+            requester_display: None,
+            accession_number: "A000000001".to_string(),
+            study_instance_uid: "2.25.1".to_string(),
+            scheduled_at: None,
+            station_ae_title: None,
+            study: None,
+            performed_at: None,
+            report_id: None,
+            reported_at: None,
+            reported_by: None,
+            cancellation_reason: None,
+            note: None,
+            created_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn instance(study: &str, series: &str) -> DicomInstance {
+        DicomInstance {
+            study_instance_uid: study.to_string(),
+            series_instance_uid: Some(series.to_string()),
+            sop_instance_uid: None,
+            sop_class_uid: None,
+            accession_number: Some("A000000001".to_string()),
+            patient_id: None,
+            patient_name: None,
+            modality: Some("CT".to_string()),
+            study_date_time: None,
+            study_description: None,
+            referring_physician_name: None,
+        }
+    }
+
+    #[test]
+    fn test_order_workflow() {
+        let mut order = order();
+        let now = Utc::now();
+        order.schedule(now, Some("CT_SCANNER_1".to_string())).unwrap();
+        assert_eq!(order.status, ImagingOrderStatus::Scheduled);
+        assert!(order.sign_off(now, None).is_err(), "not performed");
+
+        // The modality assigned its own study UID; the order takes it
+        order.link_instance(&instance("2.25.99", "2.25.99.1"), now).unwrap();
+        order.link_instance(&instance("2.25.99", "2.25.99.2"), now).unwrap();
+        order.link_instance(&instance("2.25.99", "2.25.99.1"), now).unwrap();
+        assert_eq!(order.status, ImagingOrderStatus::Performed);
+        assert_eq!(order.study_instance_uid, "2.25.99");
+        assert_eq!(order.study.as_ref().unwrap().series_instance_uids, vec!["2.25.99.1", "2.25.99.2"]);
+        assert!(order.link_instance(&instance("2.25.100", "2.25.100.1"), now).is_err(), "another study");
+        assert!(order.schedule(now, None).is_err());
+        assert!(order.cancel("Patient left".to_string()).is_err());
+
+        assert!(order.sign_off(now, None).is_err(), "no report yet");
+        order.report_id = Some(Uuid::new_v4());
+        let radiologist = Uuid::new_v4();
+        order.sign_off(now, Some(radiologist)).unwrap();
+        assert_eq!((order.status, order.reported_by), (ImagingOrderStatus::Reported, Some(radiologist)));
+        assert_eq!(order.status.request_status(), "completed");
+    }
+
+    #[test]
+    fn test_cancel_and_codes() {
+        let mut order = order();
+        order.cancel("Duplicate order".to_string()).unwrap();
+        assert_eq!(order.status.request_status(), "revoked");
+        assert!(order.link_instance(&instance("2.25.1", "2.25.1.1"), Utc::now()).is_err());
+
+        assert!(check_code_string("Modality", "MR").is_ok());
+        assert!(check_code_string("Modality", "mr").is_err());
+        assert!(check_code_string("Modality", "").is_err());
+        assert!(check_ae_title("CT_SCANNER_1").is_ok());
+        assert!(check_ae_title("CT\\1").is_err());
+        for status in ["ordered", "scheduled", "performed", "reported", "cancelled"] {
+            assert_eq!(ImagingOrderStatus::from_string(status).map(|s| s.as_str()), Some(status));
+        }
+    }
+}
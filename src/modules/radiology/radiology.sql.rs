@@ -0,0 +1,101 @@
+//! Radiology SQL Queries
+//!
+//! This file contains all SQL queries used by the radiology service.
+
+/// Place an imaging order; the accession number comes from its sequence
+pub const INSERT_ORDER: &str = r#"
+    INSERT INTO imaging_orders (id, patient_id, encounter_id, priority, code, modality, body_site, reason,
+                                requester_id, requester_display, study_instance_uid, note, created_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+    RETURNING id, patient_id, encounter_id, status, priority, code, modality, body_site, reason, requester_id,
+              requester_display, accession_number, study_instance_uid, scheduled_at, station_ae_title, study,
+              performed_at, report_id, reported_at, reported_by, cancellation_reason, note, created_by,
+              created_at, updated_at
+"#;
+
+/// Get imaging order by ID
+pub const GET_ORDER_BY_ID: &str = r#"
+    SELECT id, patient_id, encounter_id, status, priority, code, modality, body_site, reason, requester_id,
+           requester_display, accession_number, study_instance_uid, scheduled_at, station_ae_title, study,
+           performed_at, report_id, reported_at, reported_by, cancellation_reason, note, created_by,
+           created_at, updated_at
+    FROM imaging_orders
+    WHERE id = $1
+"#;
+
+/// The newest orders, optionally of one patient ($1) or in one status ($2)
+pub const LIST_ORDERS: &str = r#"
+    SELECT id, patient_id, encounter_id, status, priority, code, modality, body_site, reason, requester_id,
+           requester_display, accession_number, study_instance_uid, scheduled_at, station_ae_title, study,
+           performed_at, report_id, reported_at, reported_by, cancellation_reason, note, created_by,
+           created_at, updated_at
+    FROM imaging_orders
+    WHERE ($1::uuid IS NULL OR patient_id = $1)
+      AND ($2::text IS NULL OR status = $2)
+    ORDER BY created_at DESC
+    LIMIT 500
+"#;
+
+/// Lock an order while it is moved on
+pub const LOCK_ORDER: &str = r#"
+    SELECT id, patient_id, encounter_id, status, priority, code, modality, body_site, reason, requester_id,
+           requester_display, accession_number, study_instance_uid, scheduled_at, station_ae_title, study,
+           performed_at, report_id, reported_at, reported_by, cancellation_reason, note, created_by,
+           created_at, updated_at
+    FROM imaging_orders
+    WHERE id = $1
+    FOR UPDATE
+"#;
+
+/// Lock the order a DICOM study was acquired for: the one with its study
+/// instance UID, or else its accession number
+pub const LOCK_ORDER_FOR_STUDY: &str = r#"
+    SELECT id, patient_id, encounter_id, status, priority, code, modality, body_site, reason, requester_id,
+           requester_display, accession_number, study_instance_uid, scheduled_at, station_ae_title, study,
+           performed_at, report_id, reported_at, reported_by, cancellation_reason, note, created_by,
+           created_at, updated_at
+    FROM imaging_orders
+    WHERE study_instance_uid = $1 OR accession_number = $2
+    ORDER BY study_instance_uid = $1 DESC
+    LIMIT 1
+    FOR UPDATE
+"#;
+
+/// Save an order's progress
+pub const UPDATE_ORDER_PROGRESS: &str = r#"
+    UPDATE imaging_orders
+    SET status = $2, encounter_id = $3, study_instance_uid = $4, scheduled_at = $5, station_ae_title = $6,
+        study = $7, performed_at = $8, report_id = $9, reported_at = $10, reported_by = $11,
+        cancellation_reason = $12
+    WHERE id = $1
+"#;
+
+/// Link an order to its report, unless it already has one
+pub const UPDATE_ORDER_REPORT: &str = r#"
+    UPDATE imaging_orders
+    SET report_id = $2
+    WHERE id = $1 AND report_id IS NULL
+"#;
+
+/// Scheduled orders, optionally in [$1, $2), on one modality ($3) or on one
+/// station ($4), by scheduled time
+pub const GET_WORKLIST: &str = r#"
+    SELECT id, patient_id, encounter_id, status, priority, code, modality, body_site, reason, requester_id,
+           requester_display, accession_number, study_instance_uid, scheduled_at, station_ae_title, study,
+           performed_at, report_id, reported_at, reported_by, cancellation_reason, note, created_by,
+           created_at, updated_at
+    FROM imaging_orders
+    WHERE status = 'scheduled'
+      AND ($1::timestamptz IS NULL OR scheduled_at >= $1)
+      AND ($2::timestamptz IS NULL OR scheduled_at < $2)
+      AND ($3::text IS NULL OR modality = $3)
+      AND ($4::text IS NULL OR station_ae_title = $4)
+    ORDER BY scheduled_at
+"#;
+
+/// Patient an encounter is of
+pub const GET_ENCOUNTER_SUBJECT: &str = r#"
+    SELECT subject
+    FROM encounters
+    WHERE id = $1
+"#;
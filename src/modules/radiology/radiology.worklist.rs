@@ -0,0 +1,183 @@
+use serde_json::{json, Map, Value};
+
+use crate::models::{Gender, Patient, TaskPriority};
+use crate::modules::radiology::radiology_service::ImagingOrder;
+use crate::standards::dicom::tags::*;
+use crate::standards::dicom::Tag;
+
+/// Attribute of a dataset in the DICOM JSON model (PS3.18 F.2), keyed by
+/// its tag as eight hex digits
+fn attribute(dataset: &mut Map<String, Value>, tag: Tag, vr: &str, value: Option<Value>) {
+    let key = format!("{:04X}{:04X}", tag.0, tag.1);
+    let attribute = match value {
+        Some(value) => json!({ "vr": vr, "Value": [value] }),
+        None => json!({ "vr": vr }),
+    };
+    dataset.insert(key, attribute);
+}
+
+fn string(value: Option<&str>) -> Option<Value> {
+    value.filter(|value| !value.is_empty()).map(|value| json!(value))
+}
+
+fn person_name(name: Option<String>) -> Option<Value> {
+    name.filter(|name| !name.is_empty()).map(|name| json!({ "Alphabetic": name }))
+}
+
+/// A patient's name as a DICOM person name, `Family^Given^Middle^Prefix^Suffix`
+fn patient_name(patient: &Patient) -> Option<String> {
+    let name = patient.name.first()?;
+    let components = [
+        name.family.clone().unwrap_or_default(),
+        name.given.first().cloned().unwrap_or_default(),
+        name.given.iter().skip(1).cloned().collect::<Vec<_>>().join(" "),
+        name.prefix.join(" "),
+        name.suffix.join(" "),
+    ];
+    let joined = components.join("^").trim_end_matches('^').to_string();
+    match joined.is_empty() {
+        true => name.text.clone(),
+        false => Some(joined),
+    }
+}
+
+fn sex(gender: &Gender) -> Option<&'static str> {
+    match gender {
+        Gender::Male => Some("M"),
+        Gender::Female => Some("F"),
+        Gender::Other => Some("O"),
+        Gender::Unknown => None,
+    }
+}
+
+/// Requested procedure priority as the worklist carries it
+fn priority(priority: &TaskPriority) -> &'static str {
+    match priority {
+        TaskPriority::Stat => "STAT",
+        TaskPriority::Asap | TaskPriority::Urgent => "HIGH",
+        TaskPriority::Routine => "ROUTINE",
+    }
+}
+
+/// A scheduled order as a modality worklist item: the patient, the
+/// requested procedure and its one scheduled procedure step, as a DICOM
+/// JSON dataset. The accession number doubles as the requested procedure
+/// and step IDs; times are in UTC.
+pub fn worklist_item(order: &ImagingOrder, patient: &Patient) -> Value {
+    let description = order.code.display_text();
+    let scheduled_at = order.scheduled_at.unwrap_or(order.created_at);
+
+    let mut step = Map::new();
+    attribute(&mut step, MODALITY, "CS", string(Some(&order.modality)));
+    attribute(&mut step, SCHEDULED_STATION_AE_TITLE, "AE", string(order.station_ae_title.as_deref()));
+    attribute(
+        &mut step,
+        SCHEDULED_PROCEDURE_STEP_START_DATE,
+        "DA",
+        Some(json!(scheduled_at.format("%Y%m%d").to_string())),
+    );
+    attribute(
+        &mut step,
+        SCHEDULED_PROCEDURE_STEP_START_TIME,
+        "TM",
+        Some(json!(scheduled_at.format("%H%M%S").to_string())),
+    );
+    attribute(&mut step, SCHEDULED_PROCEDURE_STEP_DESCRIPTION, "LO", string(description.as_deref()));
+    attribute(&mut step, SCHEDULED_PROCEDURE_STEP_ID, "SH", string(Some(&order.accession_number)));
+    attribute(&mut step, SCHEDULED_PROCEDURE_STEP_STATUS, "CS", Some(json!("SCHEDULED")));
+
+    let mut item = Map::new();
+    attribute(&mut item, ACCESSION_NUMBER, "SH", string(Some(&order.accession_number)));
+    attribute(&mut item, REFERRING_PHYSICIAN_NAME, "PN", person_name(order.requester_display.clone()));
+    attribute(&mut item, TIMEZONE_OFFSET_FROM_UTC, "SH", Some(json!("+0000")));
+    attribute(&mut item, PATIENT_NAME, "PN", person_name(patient_name(patient)));
+    attribute(&mut item, PATIENT_ID, "LO", Some(json!(patient.id.to_string())));
+    attribute(
+        &mut item,
+        PATIENT_BIRTH_DATE,
+        "DA",
+        patient.birth_date.map(|date| json!(date.format("%Y%m%d").to_string())),
+    );
+    attribute(&mut item, PATIENT_SEX, "CS", sex(&patient.gender).map(|sex| json!(sex)));
+    attribute(&mut item, STUDY_INSTANCE_UID, "UI", string(Some(&order.study_instance_uid)));
+    attribute(&mut item, REQUESTED_PROCEDURE_DESCRIPTION, "LO", string(description.as_deref()));
+    attribute(&mut item, REQUESTED_PROCEDURE_ID, "SH", string(Some(&order.accession_number)));
+    attribute(&mut item, REQUESTED_PROCEDURE_PRIORITY, "SH", Some(json!(priority(&order.priority))));
+    attribute(&mut item, SCHEDULED_PROCEDURE_STEP_SEQUENCE, "SQ", Some(Value::Object(step)));
+    Value::Object(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CodeableConcept, HumanName};
+    use crate::modules::radiology::radiology_service::ImagingOrderStatus;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_worklist_item() {
+        let patient = Patient::new(
+            vec![HumanName {
+                use_type: None,
+                text: None,
+                family: Some("Doe".to_string()),
+                given: vec!["Jane".to_string(), "Q".to_string()],
+                prefix: Vec::new(),
+                suffix: Vec::new(),
+            }],
+            Vec::new(),
+            Gender::Female,
+            NaiveDate::from_ymd_opt(1980, 2, 29),
+        );
+
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 9, 30, 0).unwrap();
+        let order = ImagingOrder {
+            id: Uuid::new_v4(),
+            patient_id: patient.id,
+            encounter_id: None,
+            status: ImagingOrderStatus::Scheduled,
+            priority: TaskPriority::Stat,
+            code: CodeableConcept {
+                coding: Vec::new(),
+                text: Some("CT head without contrast".to_string()),
+            },
+            modality: "CT".to_string(),
+            body_site: None,
+            reason: None,
+            requester_id: None,
+            requester_display: Some("Smith^John".to_string()),
+            accession_number: "A000000007".to_string(),
+            study_instance_uid: "2.25.42".to_string(),
+            scheduled_at: Some(now),
+            station_ae_title: Some("CT1".to_string()),
+            study: None,
+            performed_at: None,
+            report_id: None,
+            reported_at: None,
+            reported_by: None,
+            cancellation_reason: None,
+            note: None,
+            created_by: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let item = worklist_item(&order, &patient);
+        assert_eq!(item["00100010"]["Value"][0]["Alphabetic"], "Doe^Jane^Q");
+        assert_eq!(item["00100020"]["Value"][0], patient.id.to_string());
+        assert_eq!(item["00100030"]["Value"][0], "19800229");
+        assert_eq!(item["00100040"]["Value"][0], "F");
+        assert_eq!(item["00080050"]["Value"][0], "A000000007");
+        assert_eq!(item["0020000D"]["Value"][0], "2.25.42");
+        assert_eq!(item["00401003"]["Value"][0], "STAT");
+
+        let step = &item["00400100"]["Value"][0];
+        assert_eq!(item["00400100"]["vr"], "SQ");
+        assert_eq!(step["00080060"]["Value"][0], "CT");
+        assert_eq!(step["00400001"]["Value"][0], "CT1");
+        assert_eq!(step["00400002"]["Value"][0], "20240506");
+        assert_eq!(step["00400003"]["Value"][0], "093000");
+        assert_eq!(step["00400007"]["Value"][0], "CT head without contrast");
+    }
+}
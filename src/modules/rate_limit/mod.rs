@@ -0,0 +1,44 @@
+//! Rate Limit Module
+//!
+//! This module provides abuse protection for the API including:
+//! - Token buckets per client IP and per verified user
+//! - Client IPs from the connection, or from `X-Forwarded-For` set by trusted proxies
+//! - Separate, smaller budgets for searches and bulk exports
+//! - Buckets in memory, or in Redis to share them between instances
+//! - 429 responses with `Retry-After`
+
+#[path = "rate_limit.service.rs"]
+pub mod rate_limit_service;
+#[path = "rate_limit.store.rs"]
+pub mod rate_limit_store;
+#[path = "rate_limit.middleware.rs"]
+pub mod rate_limit_middleware;
+
+pub use rate_limit_middleware::RateLimitMiddleware;
+pub use rate_limit_service::{
+    Budget, EndpointClass, RateLimitClient, RateLimitConfig, RateLimitService, TrustedProxy,
+};
+pub use rate_limit_store::{MemoryStore, RateLimitStore, Take};
+
+use std::sync::Arc;
+
+/// Rate Limit Module Configuration
+pub struct RateLimitModule {
+    pub service: Arc<RateLimitService>,
+    pub middleware: Arc<RateLimitMiddleware>,
+}
+
+impl RateLimitModule {
+    /// Create a new Rate Limit Module with dependency injection
+    pub fn new(config: RateLimitConfig) -> Self {
+        let service = Arc::new(RateLimitService::from_config(config));
+        let middleware = Arc::new(RateLimitMiddleware::new(service.clone()));
+
+        Self { service, middleware }
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<RateLimitService> {
+        self.service.clone()
+    }
+}
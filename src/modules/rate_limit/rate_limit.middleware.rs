@@ -0,0 +1,69 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::modules::rate_limit::rate_limit_service::EndpointClass;
+use crate::modules::rate_limit::rate_limit_store::Take;
+use crate::modules::rate_limit::RateLimitService;
+
+/// Rate limiting middleware
+pub struct RateLimitMiddleware {
+    service: Arc<RateLimitService>,
+}
+
+impl RateLimitMiddleware {
+    pub fn new(service: Arc<RateLimitService>) -> Self {
+        Self { service }
+    }
+
+    pub fn service(&self) -> Arc<RateLimitService> {
+        self.service.clone()
+    }
+
+    /// Answer 429 with `Retry-After` once the client's budget is spent. If
+    /// the bucket store cannot be reached the request is let through, so an
+    /// outage of the limiter is not an outage of the API. Clients are told
+    /// apart by the connection's address, which needs the server to be run
+    /// with `into_make_service_with_connect_info`. The client is left in the
+    /// request's extensions for handlers that record where it came from.
+    pub async fn limit(
+        State(service): State<Arc<RateLimitService>>,
+        headers: HeaderMap,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let class = EndpointClass::of(request.method(), request.uri().path(), request.uri().query());
+        let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+        let client = service.client(peer, &headers);
+        request.extensions_mut().insert(client.clone());
+
+        match service.check(&client, class).await {
+            Ok(Take::Allowed) => next.run(request).await,
+            Ok(Take::Limited { retry_after_secs }) => {
+                let mut response = (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(json!({
+                        "error": "Too many requests",
+                        "message": format!("Rate limit exceeded; retry after {} seconds", retry_after_secs),
+                    })),
+                )
+                    .into_response();
+                if let Ok(value) = HeaderValue::from_str(&retry_after_secs.to_string()) {
+                    response.headers_mut().insert(header::RETRY_AFTER, value);
+                }
+                response
+            }
+            Err(e) => {
+                tracing::error!("Rate limit check failed, allowing request: {}", e);
+                next.run(request).await
+            }
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use axum::http::{HeaderMap, Method};
+use std::net::IpAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::auth_jwt::token_verifier;
+use crate::modules::rate_limit::rate_limit_store::{MemoryStore, RateLimitStore, Take};
+
+/// A token bucket's size: `burst` requests at once, refilled at `per_minute`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub per_minute: f64,
+    pub burst: u32,
+}
+
+impl Budget {
+    pub const fn new(per_minute: f64, burst: u32) -> Self {
+        Self { per_minute, burst }
+    }
+
+    /// Parse `<per minute>,<burst>`, e.g. `600,100`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (per_minute, burst) = value.split_once(',')?;
+        let per_minute: f64 = per_minute.trim().parse().ok()?;
+        let burst: u32 = burst.trim().parse().ok()?;
+        (per_minute > 0.0 && burst > 0).then_some(Self { per_minute, burst })
+    }
+
+    /// Tokens after `elapsed_secs` of refilling
+    pub fn refill(&self, tokens: f64, elapsed_secs: f64) -> f64 {
+        (tokens + elapsed_secs.max(0.0) * self.per_minute / 60.0).min(self.burst as f64)
+    }
+
+    /// Take a token if there is one; returns the tokens left
+    pub fn take(&self, tokens: f64) -> (f64, Take) {
+        if tokens >= 1.0 {
+            (tokens - 1.0, Take::Allowed)
+        } else {
+            (tokens, Take::Limited { retry_after_secs: self.retry_after(tokens) })
+        }
+    }
+
+    /// Whole seconds until a bucket holding `tokens` holds one
+    pub fn retry_after(&self, tokens: f64) -> u64 {
+        ((1.0 - tokens).max(0.0) * 60.0 / self.per_minute).ceil().max(1.0) as u64
+    }
+
+    /// Seconds an empty bucket takes to fill
+    pub fn refill_secs(&self) -> f64 {
+        self.burst as f64 * 60.0 / self.per_minute
+    }
+}
+
+/// Endpoints with their own budgets, as they cost far more than a read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointClass {
+    Standard,
+    /// Searches and GraphQL queries
+    Search,
+    /// `$export` and other bulk exports
+    BulkExport,
+}
+
+impl EndpointClass {
+    pub fn of(method: &Method, path: &str, query: Option<&str>) -> Self {
+        if path.contains("$export") || path.ends_with("/export") {
+            EndpointClass::BulkExport
+        } else if path.ends_with("/_search")
+            || path.ends_with("/graphql")
+            || (method == Method::GET && query.is_some_and(|query| !query.is_empty()))
+        {
+            EndpointClass::Search
+        } else {
+            EndpointClass::Standard
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndpointClass::Standard => "standard",
+            EndpointClass::Search => "search",
+            EndpointClass::BulkExport => "bulk_export",
+        }
+    }
+}
+
+/// A proxy address or network, e.g. `10.0.0.0/8`, whose forwarding headers
+/// are believed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl TrustedProxy {
+    /// Parse an address, or a network in CIDR notation
+    pub fn parse(value: &str) -> Option<Self> {
+        let (address, prefix_len) = match value.trim().split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len.parse::<u8>().ok()?)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = address.parse().ok()?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        (prefix_len <= max_len).then_some(Self { network, prefix_len })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Rate limit settings
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Every request, by client IP
+    pub per_ip: Budget,
+    /// Requests with a verified user token
+    pub per_user: Budget,
+    /// Searches, per user or else IP, instead of the budgets above. API
+    /// keys are held to their own limits once verified.
+    pub search: Budget,
+    /// Bulk exports, likewise
+    pub bulk_export: Budget,
+    /// `redis://` URL to share buckets between instances; needs the `redis`
+    /// feature. Buckets are kept in memory without one.
+    pub redis_url: Option<String>,
+    /// Proxies whose `X-Forwarded-For` is believed; a request from anywhere
+    /// else is counted against its connection's address
+    pub trusted_proxies: Vec<TrustedProxy>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            per_ip: Budget::new(600.0, 100),
+            per_user: Budget::new(600.0, 100),
+            search: Budget::new(60.0, 20),
+            bulk_export: Budget::new(0.2, 2),
+            redis_url: None,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Settings from `RATE_LIMIT_*` variables; budgets are `<per minute>,<burst>`
+    /// and `RATE_LIMIT_TRUSTED_PROXIES` is a comma-separated list of
+    /// addresses and CIDR networks
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let budget = |name: &str, default: Budget| {
+            std::env::var(name).ok().and_then(|value| Budget::parse(&value)).unwrap_or(default)
+        };
+
+        Self {
+            enabled: std::env::var("RATE_LIMIT_ENABLED")
+                .ok()
+                .and_then(|enabled| enabled.parse().ok())
+                .unwrap_or(defaults.enabled),
+            per_ip: budget("RATE_LIMIT_PER_IP", defaults.per_ip),
+            per_user: budget("RATE_LIMIT_PER_USER", defaults.per_user),
+            search: budget("RATE_LIMIT_SEARCH", defaults.search),
+            bulk_export: budget("RATE_LIMIT_BULK_EXPORT", defaults.bulk_export),
+            redis_url: std::env::var("RATE_LIMIT_REDIS_URL").ok(),
+            trusted_proxies: std::env::var("RATE_LIMIT_TRUSTED_PROXIES")
+                .map(|proxies| {
+                    proxies
+                        .split(',')
+                        .filter(|proxy| !proxy.trim().is_empty())
+                        .filter_map(|proxy| {
+                            let parsed = TrustedProxy::parse(proxy);
+                            if parsed.is_none() {
+                                tracing::warn!("Ignoring invalid trusted proxy: {}", proxy);
+                            }
+                            parsed
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Store for the configured backend. A Redis URL without the `redis`
+    /// feature compiled in is a configuration error rather than silently
+    /// per-instance limits.
+    pub fn store(&self) -> Result<Arc<dyn RateLimitStore>, HimsError> {
+        match &self.redis_url {
+            #[cfg(feature = "redis")]
+            Some(url) => Ok(Arc::new(crate::modules::rate_limit::rate_limit_store::RedisStore::new(url)?)),
+            #[cfg(not(feature = "redis"))]
+            Some(url) => Err(HimsError::ConfigurationError {
+                message: format!("RATE_LIMIT_REDIS_URL is set to {} but the redis feature is not enabled", url),
+            }),
+            None => Ok(Arc::new(MemoryStore::new())),
+        }
+    }
+}
+
+/// Who a request is counted against. Only identities the client cannot
+/// choose count: the connection's address, or the address a trusted proxy
+/// forwarded, and the subject of a verified token.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitClient {
+    pub ip_address: Option<String>,
+    pub user_id: Option<Uuid>,
+    /// Whether the request arrived from a trusted proxy, whose other
+    /// headers about the client, such as its location, can be believed
+    pub via_trusted_proxy: bool,
+}
+
+impl RateLimitClient {
+    /// Client of a request that arrived from `peer`
+    pub fn from_request(peer: Option<IpAddr>, headers: &HeaderMap, trusted_proxies: &[TrustedProxy]) -> Self {
+        Self {
+            ip_address: Self::client_ip(peer, headers, trusted_proxies).map(|ip| ip.to_string()),
+            user_id: Self::verified_user(headers),
+            via_trusted_proxy: peer.is_some_and(|peer| trusted_proxies.iter().any(|proxy| proxy.contains(&peer))),
+        }
+    }
+
+    /// The peer address, unless the peer is a trusted proxy: then the
+    /// nearest address in `X-Forwarded-For` that is not a trusted proxy
+    fn client_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted_proxies: &[TrustedProxy]) -> Option<IpAddr> {
+        let trusted = |ip: &IpAddr| trusted_proxies.iter().any(|proxy| proxy.contains(ip));
+        let peer = peer?;
+        if !trusted(&peer) {
+            return Some(peer);
+        }
+
+        let forwarded: Vec<Option<IpAddr>> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|hop| hop.trim().parse().ok())
+            .collect();
+        // Each proxy appends the address it received from, so the chain is
+        // only believed from the right up to the first untrusted hop
+        let client = forwarded.into_iter().rev().find(|hop| !hop.is_some_and(|hop| trusted(&hop)));
+        Some(client.flatten().unwrap_or(peer))
+    }
+
+    /// Subject of a verified bearer token
+    fn verified_user(headers: &HeaderMap) -> Option<Uuid> {
+        let token = headers
+            .get("authorization")?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        let claims = token_verifier()?.validate_access_token(token).ok()?;
+        Uuid::parse_str(&claims.sub).ok()
+    }
+
+    /// Most specific identity, which expensive endpoints are counted
+    /// against. Clients without any share one bucket rather than none.
+    fn principal(&self) -> String {
+        match (self.user_id, &self.ip_address) {
+            (Some(user_id), _) => format!("user:{}", user_id),
+            (None, Some(ip)) => format!("ip:{}", ip),
+            (None, None) => "ip:unknown".to_string(),
+        }
+    }
+}
+
+/// Rate limiting with token buckets per client and endpoint class
+pub struct RateLimitService {
+    config: RateLimitConfig,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimitService {
+    pub fn new(config: RateLimitConfig, store: Arc<dyn RateLimitStore>) -> Self {
+        Self { config, store }
+    }
+
+    /// Service for the configured backend, falling back to memory when the
+    /// backend is misconfigured so requests are still limited per instance
+    pub fn from_config(config: RateLimitConfig) -> Self {
+        let store = config.store().unwrap_or_else(|e| {
+            tracing::error!("Rate limiting falls back to in-memory buckets: {}", e);
+            Arc::new(MemoryStore::new())
+        });
+        Self::new(config, store)
+    }
+
+    /// Buckets a request draws from: expensive endpoints draw from their
+    /// own budget for the client, others from every identity presented
+    fn buckets(&self, client: &RateLimitClient, class: EndpointClass) -> Vec<(String, Budget)> {
+        let expensive = match class {
+            EndpointClass::Search => Some(self.config.search),
+            EndpointClass::BulkExport => Some(self.config.bulk_export),
+            EndpointClass::Standard => None,
+        };
+        if let Some(budget) = expensive {
+            return vec![(format!("{}:{}", client.principal(), class.as_str()), budget)];
+        }
+
+        let ip = client.ip_address.as_deref().unwrap_or("unknown");
+        let mut buckets = vec![(format!("ip:{}", ip), self.config.per_ip)];
+        if let Some(user_id) = client.user_id {
+            buckets.push((format!("user:{}", user_id), self.config.per_user));
+        }
+        buckets
+    }
+
+    /// Client of a request that arrived from `peer`, believing forwarding
+    /// headers only from the configured proxies
+    pub fn client(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> RateLimitClient {
+        RateLimitClient::from_request(peer, headers, &self.config.trusted_proxies)
+    }
+
+    /// Count a request. When limited, the seconds until every exhausted
+    /// bucket has a token again.
+    pub async fn check(&self, client: &RateLimitClient, class: EndpointClass) -> Result<Take, HimsError> {
+        if !self.config.enabled {
+            return Ok(Take::Allowed);
+        }
+
+        let mut retry_after = None;
+        for (key, budget) in self.buckets(client, class) {
+            if let Take::Limited { retry_after_secs } = self.store.take(&key, budget).await? {
+                tracing::warn!("Rate limit exceeded for {}", key);
+                retry_after = Some(retry_after.unwrap_or(0).max(retry_after_secs));
+            }
+        }
+
+        Ok(match retry_after {
+            Some(retry_after_secs) => Take::Limited { retry_after_secs },
+            None => Take::Allowed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_budget_parse() {
+        assert_eq!(Budget::parse("600,100"), Some(Budget::new(600.0, 100)));
+        assert_eq!(Budget::parse(" 0.5 , 2 "), Some(Budget::new(0.5, 2)));
+        assert_eq!(Budget::parse("600"), None);
+        assert_eq!(Budget::parse("0,10"), None);
+    }
+
+    #[test]
+    fn test_memory_bucket_refills() {
+        let store = MemoryStore::new();
+        let budget = Budget::new(60.0, 2);
+        let start = Instant::now();
+
+        assert_eq!(store.take_at("ip:10.0.0.1", budget, start), Take::Allowed);
+        assert_eq!(store.take_at("ip:10.0.0.1", budget, start), Take::Allowed);
+        assert_eq!(
+            store.take_at("ip:10.0.0.1", budget, start),
+            Take::Limited { retry_after_secs: 1 }
+        );
+        // Other clients have their own bucket
+        assert_eq!(store.take_at("ip:10.0.0.2", budget, start), Take::Allowed);
+        // One token a second
+        assert_eq!(store.take_at("ip:10.0.0.1", budget, start + Duration::from_secs(1)), Take::Allowed);
+    }
+
+    #[test]
+    fn test_memory_store_is_bounded() {
+        let store = MemoryStore::with_capacity(2);
+        let budget = Budget::new(60.0, 2);
+        let start = Instant::now();
+
+        assert_eq!(store.take_at("ip:10.0.0.1", budget, start), Take::Allowed);
+        assert_eq!(store.take_at("ip:10.0.0.2", budget, start), Take::Allowed);
+        // Full of active clients: new ones are limited, known ones are not
+        assert_eq!(
+            store.take_at("ip:10.0.0.3", budget, start),
+            Take::Limited { retry_after_secs: 1 }
+        );
+        assert_eq!(store.take_at("ip:10.0.0.1", budget, start), Take::Allowed);
+        // Once the first buckets have refilled they are swept for new clients
+        let later = start + Duration::from_secs(3);
+        assert_eq!(store.take_at("ip:10.0.0.3", budget, later), Take::Allowed);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let budget = Budget::new(0.2, 2);
+        assert_eq!(budget.retry_after(0.0), 300);
+        assert_eq!(budget.retry_after(0.5), 150);
+        assert_eq!(Budget::new(6000.0, 10).retry_after(0.9), 1);
+    }
+
+    #[test]
+    fn test_endpoint_class() {
+        assert_eq!(EndpointClass::of(&Method::GET, "/api/v1/patients/123", None), EndpointClass::Standard);
+        assert_eq!(
+            EndpointClass::of(&Method::GET, "/api/v1/patients", Some("name=smith")),
+            EndpointClass::Search
+        );
+        assert_eq!(EndpointClass::of(&Method::POST, "/api/v1/patients/_search", None), EndpointClass::Search);
+        assert_eq!(EndpointClass::of(&Method::POST, "/api/v1/graphql", None), EndpointClass::Search);
+        assert_eq!(EndpointClass::of(&Method::GET, "/api/v1/Patient/$export", None), EndpointClass::BulkExport);
+        assert_eq!(EndpointClass::of(&Method::POST, "/api/v1/patients", None), EndpointClass::Standard);
+    }
+
+    #[tokio::test]
+    async fn test_expensive_endpoints_have_separate_budgets() {
+        let config = RateLimitConfig {
+            search: Budget::new(60.0, 1),
+            ..RateLimitConfig::default()
+        };
+        let service = RateLimitService::new(config, Arc::new(MemoryStore::new()));
+        let client = RateLimitClient {
+            ip_address: Some("10.0.0.1".to_string()),
+            user_id: Some(Uuid::new_v4()),
+            via_trusted_proxy: false,
+        };
+
+        assert_eq!(service.check(&client, EndpointClass::Search).await.unwrap(), Take::Allowed);
+        assert!(matches!(
+            service.check(&client, EndpointClass::Search).await.unwrap(),
+            Take::Limited { .. }
+        ));
+        // Reads are unaffected by an exhausted search budget
+        assert_eq!(service.check(&client, EndpointClass::Standard).await.unwrap(), Take::Allowed);
+    }
+
+    #[tokio::test]
+    async fn test_disabled() {
+        let config = RateLimitConfig {
+            enabled: false,
+            per_ip: Budget::new(1.0, 1),
+            ..RateLimitConfig::default()
+        };
+        let service = RateLimitService::new(config, Arc::new(MemoryStore::new()));
+        let client = RateLimitClient {
+            ip_address: Some("10.0.0.1".to_string()),
+            ..RateLimitClient::default()
+        };
+        for _ in 0..3 {
+            assert_eq!(service.check(&client, EndpointClass::Standard).await.unwrap(), Take::Allowed);
+        }
+    }
+
+    #[test]
+    fn test_trusted_proxy() {
+        let proxy = TrustedProxy::parse("10.0.0.0/8").unwrap();
+        assert!(proxy.contains(&"10.1.2.3".parse().unwrap()));
+        assert!(!proxy.contains(&"11.0.0.1".parse().unwrap()));
+        assert!(!proxy.contains(&"::ffff:10.0.0.1".parse().unwrap()));
+        assert!(TrustedProxy::parse("192.0.2.7").unwrap().contains(&"192.0.2.7".parse().unwrap()));
+        assert!(TrustedProxy::parse("fd00::/8").unwrap().contains(&"fd12::1".parse().unwrap()));
+        assert!(TrustedProxy::parse("0.0.0.0/0").unwrap().contains(&"203.0.113.9".parse().unwrap()));
+        assert_eq!(TrustedProxy::parse("10.0.0.0/33"), None);
+        assert_eq!(TrustedProxy::parse("proxy.local"), None);
+    }
+
+    #[test]
+    fn test_client_identity() {
+        let proxies = [TrustedProxy::parse("10.0.0.0/8").unwrap()];
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "198.51.100.4, 203.0.113.9, 10.0.0.2".parse().unwrap());
+        headers.insert("x-user-id", Uuid::new_v4().to_string().parse().unwrap());
+        headers.insert("authorization", "Bearer forged".parse().unwrap());
+
+        // Forwarding headers from an untrusted peer are ignored
+        let peer: IpAddr = "192.0.2.50".parse().unwrap();
+        let client = RateLimitClient::from_request(Some(peer), &headers, &proxies);
+        assert_eq!(client.ip_address.as_deref(), Some("192.0.2.50"));
+        assert_eq!(client.user_id, None);
+        assert!(!client.via_trusted_proxy);
+
+        // Through a trusted proxy, the nearest untrusted hop is the client;
+        // hops it prepended itself are not believed
+        let client = RateLimitClient::from_request(Some("10.0.0.1".parse().unwrap()), &headers, &proxies);
+        assert_eq!(client.ip_address.as_deref(), Some("203.0.113.9"));
+        assert!(client.via_trusted_proxy);
+
+        assert_eq!(RateLimitClient::from_request(None, &headers, &proxies).principal(), "ip:unknown");
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_clients_share_a_bucket() {
+        let config = RateLimitConfig {
+            bulk_export: Budget::new(60.0, 1),
+            ..RateLimitConfig::default()
+        };
+        let service = RateLimitService::new(config, Arc::new(MemoryStore::new()));
+        let anonymous = RateLimitClient::default();
+
+        assert_eq!(service.check(&anonymous, EndpointClass::BulkExport).await.unwrap(), Take::Allowed);
+        assert!(matches!(
+            service.check(&anonymous, EndpointClass::BulkExport).await.unwrap(),
+            Take::Limited { .. }
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::core::HimsError;
+use crate::modules::rate_limit::rate_limit_service::Budget;
+
+/// Result of taking a token from a bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Take {
+    Allowed,
+    /// Seconds until the bucket holds a token again
+    Limited { retry_after_secs: u64 },
+}
+
+/// Token buckets keyed by client and budget
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Take one token from the bucket under `key`, which starts full
+    async fn take(&self, key: &str, budget: Budget) -> Result<Take, HimsError>;
+}
+
+/// Buckets in process memory. Each server instance limits on its own, so
+/// with N replicas a client gets up to N times its budget.
+///
+/// Buckets idle long enough to have refilled are the same as absent ones, so
+/// they are swept away every `SWEEP_INTERVAL`, and sooner when the store is
+/// full. A full store with nothing to sweep limits new clients rather than
+/// growing past its capacity.
+#[derive(Debug)]
+pub struct MemoryStore {
+    buckets: Mutex<Buckets>,
+    capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    /// Tokens, when they were counted, and seconds until the bucket is full
+    /// again by its budget
+    by_key: HashMap<String, (f64, Instant, f64)>,
+    swept_at: Option<Instant>,
+}
+
+impl Buckets {
+    fn sweep(&mut self, now: Instant) {
+        self.by_key
+            .retain(|_, (_, at, full_after)| now.duration_since(*at).as_secs_f64() < *full_after);
+        self.swept_at = Some(now);
+    }
+
+    fn swept_within(&self, now: Instant, interval: Duration) -> bool {
+        self.swept_at.is_some_and(|at| now.duration_since(at) < interval)
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::with_capacity(Self::MAX_BUCKETS)
+    }
+}
+
+impl MemoryStore {
+    const MAX_BUCKETS: usize = 100_000;
+
+    /// How often idle buckets are swept
+    const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// How often a full store is swept at most, so a flood of new clients
+    /// does not scan every bucket on every request
+    const FULL_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store holding at most `capacity` buckets
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buckets: Mutex::new(Buckets::default()),
+            capacity,
+        }
+    }
+
+    pub fn take_at(&self, key: &str, budget: Budget, now: Instant) -> Take {
+        // Buckets are consistent after any panic, as each is written whole
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !buckets.swept_within(now, Self::SWEEP_INTERVAL) {
+            buckets.sweep(now);
+        }
+
+        if !buckets.by_key.contains_key(key) && buckets.by_key.len() >= self.capacity {
+            if !buckets.swept_within(now, Self::FULL_SWEEP_INTERVAL) {
+                buckets.sweep(now);
+                if buckets.by_key.len() >= self.capacity {
+                    tracing::warn!("Rate limit store is full with {} clients; limiting new ones", buckets.by_key.len());
+                }
+            }
+            if buckets.by_key.len() >= self.capacity {
+                return Take::Limited {
+                    retry_after_secs: Self::FULL_SWEEP_INTERVAL.as_secs().max(1),
+                };
+            }
+        }
+
+        let bucket = buckets
+            .by_key
+            .entry(key.to_string())
+            .or_insert((budget.burst as f64, now, budget.refill_secs()));
+        let tokens = budget.refill(bucket.0, now.duration_since(bucket.1).as_secs_f64());
+        let (tokens, take) = budget.take(tokens);
+        *bucket = (tokens, now, budget.refill_secs());
+        take
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, budget: Budget) -> Result<Take, HimsError> {
+        Ok(self.take_at(key, budget, Instant::now()))
+    }
+}
+
+/// Buckets shared by every instance through Redis, refilled and taken in
+/// one script so concurrent requests cannot overdraw them
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    client: redis::Client,
+    connection: tokio::sync::OnceCell<redis::aio::ConnectionManager>,
+    script: redis::Script,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    /// Store for a `redis://` URL. The connection is made on first use.
+    pub fn new(url: &str) -> Result<Self, HimsError> {
+        let client = redis::Client::open(url).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Invalid rate limit Redis URL: {}", e),
+        })?;
+
+        Ok(Self {
+            client,
+            connection: tokio::sync::OnceCell::new(),
+            script: redis::Script::new(
+                r#"
+                local burst = tonumber(ARGV[1])
+                local per_sec = tonumber(ARGV[2])
+                local now = tonumber(ARGV[3])
+                local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+                local tokens = tonumber(bucket[1]) or burst
+                local at = tonumber(bucket[2]) or now
+                tokens = math.min(burst, tokens + math.max(0, now - at) * per_sec)
+                local allowed = 0
+                if tokens >= 1 then
+                    tokens = tokens - 1
+                    allowed = 1
+                end
+                redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'at', tostring(now))
+                redis.call('EXPIRE', KEYS[1], math.ceil(burst / per_sec) + 1)
+                return {allowed, tostring(tokens)}
+                "#,
+            ),
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    async fn take(&self, key: &str, budget: Budget) -> Result<Take, HimsError> {
+        let redis_error = |e: redis::RedisError| HimsError::NetworkError {
+            message: format!("Rate limit Redis error: {}", e),
+        };
+
+        let connection = self
+            .connection
+            .get_or_try_init(|| redis::aio::ConnectionManager::new(self.client.clone()))
+            .await
+            .map_err(redis_error)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs_f64())
+            .unwrap_or_default();
+
+        let (allowed, tokens): (i64, String) = self
+            .script
+            .key(format!("hims:ratelimit:{}", key))
+            .arg(budget.burst)
+            .arg(budget.per_minute / 60.0)
+            .arg(now)
+            .invoke_async(&mut connection.clone())
+            .await
+            .map_err(redis_error)?;
+
+        if allowed == 1 {
+            Ok(Take::Allowed)
+        } else {
+            Ok(Take::Limited {
+                retry_after_secs: budget.retry_after(tokens.parse().unwrap_or_default()),
+            })
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Referral Module
+//!
+//! This module provides patient referrals as FHIR R4 ServiceRequests:
+//! - Outbound referrals with their reasons, priority and target specialty
+//! - Referral letters as PDF, and as C-CDA Referral Notes
+//! - Acceptance or decline by the referred-to party, and the appointment
+//!   booked for an accepted referral
+//! - Inbound referrals received as FHIR ServiceRequests or HL7 v2 REF^I12
+//!   messages, filed once per sender's number
+//! - Versioned updates guarded by `If-Match`
+//! - Access checked against the patient, and every access audited
+
+#[path = "referral.controller.rs"]
+pub mod referral_controller;
+#[path = "referral.inbound.rs"]
+pub mod referral_inbound;
+#[path = "referral.letter.rs"]
+pub mod referral_letter;
+#[path = "referral.service.rs"]
+pub mod referral_service;
+#[path = "referral.sql.rs"]
+pub mod referral_sql;
+
+pub use referral_controller::ReferralController;
+pub use referral_service::{ReferralRequest, ReferralSearch, ReferralSearchResult, ReferralService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::audit::AuditService;
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::condition::ConditionService;
+use crate::modules::events::EventBus;
+use crate::modules::patient::PatientService;
+use crate::modules::role::RoleService;
+use crate::utils::api_router::ApiRouter;
+
+/// Referral Module Configuration
+pub struct ReferralModule {
+    pub service: Arc<ReferralService>,
+    pub controller: Arc<ReferralController>,
+}
+
+impl ReferralModule {
+    /// Create a new Referral Module reading patients and their problems for
+    /// referral letters
+    pub fn new(
+        db_pool: PgPool,
+        events: EventBus,
+        patients: Arc<PatientService>,
+        conditions: Arc<ConditionService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        let role_service = Arc::new(RoleService::new(db_pool.clone()));
+        let audit_service = Arc::new(AuditService::new(db_pool.clone()));
+        let service = Arc::new(ReferralService::new(db_pool, events, patients, conditions));
+        let controller = Arc::new(ReferralController::new(
+            service.clone(),
+            authorization_engine,
+            role_service,
+            audit_service,
+        ));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<ReferralService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,713 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{
+    PATIENT_REFERRAL_CODE, REFERRAL_ACCEPTANCE_EXTENSION, REFERRAL_APPOINTMENT_EXTENSION,
+    SERVICE_REQUEST_RESOURCE_TYPE, SNOMED_CT_SYSTEM,
+};
+use crate::models::{
+    AuditAction, AuditEventType, AuditLog, CodeableConcept, Coding, Identifier, Reference, Referral, ResourceMeta,
+};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::referral::referral_inbound::{parse_fhir, parse_hl7};
+use crate::modules::referral::referral_service::{ReferralRequest, ReferralSearch, ReferralSearchResult};
+use crate::modules::referral::ReferralService;
+use crate::modules::role::RoleService;
+use crate::standards::hl7v2::generator::Hl7Generator;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::etag::{if_match_version, precondition_status, versioned, Versioned};
+use crate::utils::fhir_search::{EntrySearch, SearchEntryMode};
+use crate::utils::pagination::{page_links, BundleLink};
+
+/// FHIR R4 ServiceRequest as a referral is served
+#[derive(Debug, Serialize)]
+pub struct ServiceRequestResponse {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    /// Acceptance by the referred-to party, and the appointment booked
+    pub extension: Vec<ServiceRequestExtension>,
+    /// The sender's number of an inbound referral
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    pub status: String,
+    pub intent: String,
+    pub category: Vec<CodeableConcept>,
+    pub priority: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    pub subject: Reference,
+    #[serde(rename = "authoredOn")]
+    pub authored_on: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester: Option<DisplayReference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(rename = "performerType")]
+    pub performer_type: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<DisplayReference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(rename = "reasonCode")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(rename = "reasonReference")]
+    pub reason_reference: Vec<Reference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+/// FHIR Extension with the value types referrals use
+#[derive(Debug, Serialize)]
+pub struct ServiceRequestExtension {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "valueCode")]
+    pub value_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "valueReference")]
+    pub value_reference: Option<Reference>,
+}
+
+/// FHIR Reference that may only name its target, as referrals to and from
+/// other organizations do
+#[derive(Debug, Serialize)]
+pub struct DisplayReference {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+/// FHIR Annotation, as ServiceRequest.note carries it
+#[derive(Debug, Serialize)]
+pub struct Annotation {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceRequestBundle {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub link: Vec<BundleLink>,
+    pub entry: Vec<ServiceRequestBundleEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceRequestBundleEntry {
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    pub resource: ServiceRequestResponse,
+    /// Set in searchsets only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<EntrySearch>,
+}
+
+/// The referred-to party's note on accepting a referral
+#[derive(Debug, Default, Deserialize)]
+pub struct AcceptRequest {
+    pub note: Option<String>,
+}
+
+/// Why a referral is declined
+#[derive(Debug, Deserialize)]
+pub struct DeclineRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Referral controller for FHIR R4 ServiceRequests, their letters and
+/// referrals received from other organizations. A referral belongs to its
+/// patient's chart: reading it or its letters takes read access to the
+/// patient and changing or answering it update access. Referrals received
+/// from other organizations name a patient still to be matched, so filing
+/// them takes the create permission instead. Each is audited against the
+/// patient.
+pub struct ReferralController {
+    state: ReferralState,
+}
+
+#[derive(Clone)]
+pub struct ReferralState {
+    service: Arc<ReferralService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    role_service: Arc<RoleService>,
+    audit_service: Arc<AuditService>,
+}
+
+impl ReferralController {
+    /// Create new controller with injected services
+    pub fn new(
+        referral_service: Arc<ReferralService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        role_service: Arc<RoleService>,
+        audit_service: Arc<AuditService>,
+    ) -> Self {
+        Self {
+            state: ReferralState {
+                service: referral_service,
+                authorization_engine,
+                role_service,
+                audit_service,
+            },
+        }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_referral, "Refer a patient")
+            .get("/", Self::search_referrals, "Search referrals with query parameters")
+            .post("/inbound", Self::receive_fhir, "Receive a referral as a FHIR ServiceRequest")
+            .post("/inbound/hl7", Self::receive_hl7, "Receive a referral as an HL7 v2 REF^I12 message")
+            .get("/:id", Self::get_referral, "Get referral by ID")
+            .put("/:id", Self::update_referral, "Update referral")
+            .delete("/:id", Self::delete_referral, "Delete referral")
+            .post("/:id/accept", Self::accept_referral, "Accept a referral")
+            .post("/:id/decline", Self::decline_referral, "Decline a referral")
+            .put(
+                "/:id/appointment/:appointment_id",
+                Self::link_appointment,
+                "Link the appointment booked for a referral",
+            )
+            .get("/:id/letter", Self::letter_pdf, "Get the referral letter as a PDF")
+            .get("/:id/letter/ccda", Self::letter_ccda, "Get the referral as a C-CDA Referral Note")
+            .with_state(self.state.clone())
+    }
+
+    /// Refer a patient
+    pub async fn create_referral(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Json(request): Json<ReferralRequest>,
+    ) -> Result<(StatusCode, Versioned<ServiceRequestResponse>), ErrorReply> {
+        tracing::info!("Creating referral for patient {}", request.patient_id);
+
+        let context = "Failed to create referral";
+        Self::authorize(&state, &auth, &headers, Action::Update, request.patient_id, context).await?;
+        match state.service.create(request).await {
+            Ok(referral) => {
+                tracing::info!("Referral created successfully: {}", referral.id);
+                let event = (AuditEventType::Create, AuditAction::Create);
+                Self::audit(&state, &auth, event, &referral, "Referral created", context).await?;
+                Ok((StatusCode::CREATED, versioned(&referral.meta.clone(), Self::referral_to_response(referral))))
+            }
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Get referral by ID
+    pub async fn get_referral(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Versioned<ServiceRequestResponse>, ErrorReply> {
+        let context = "Failed to get referral";
+        let referral = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Read, referral.patient_id, context).await?;
+        let event = (AuditEventType::Access, AuditAction::Read);
+        Self::audit(&state, &auth, event, &referral, "Referral viewed", context).await?;
+        Ok(versioned(&referral.meta.clone(), Self::referral_to_response(referral)))
+    }
+
+    /// Search referrals with query parameters. Referrals of patients the
+    /// user may not read are left out of the page.
+    pub async fn search_referrals(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<Json<ServiceRequestBundle>, ErrorReply> {
+        tracing::info!("Searching referrals with params: {:?}", params);
+
+        let context = "Failed to search referrals";
+        let search =
+            ReferralSearch::from_params(&params).map_err(|e| Self::error_response("Invalid search parameters", e))?;
+        let mut result = state.service.search(&search).await.map_err(|e| Self::error_response(context, e))?;
+
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), &auth, &headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        let found = result.referrals.len();
+        result.referrals = authorizer
+            .retain(Action::Read, result.referrals, |referral| Resource::Patient(referral.patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        if result.referrals.len() < found {
+            let withheld = found - result.referrals.len();
+            tracing::info!("Withheld {} of {} referrals from user {}", withheld, found, auth.user_id);
+        }
+        tracing::info!("Found {} referrals", result.referrals.len());
+        for referral in &result.referrals {
+            let event = (AuditEventType::Access, AuditAction::Read);
+            Self::audit(&state, &auth, event, referral, "Referral search", context).await?;
+        }
+
+        let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+        Ok(Json(Self::referrals_to_bundle(result, link)))
+    }
+
+    /// Update referral; `If-Match` must name the version being replaced
+    pub async fn update_referral(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ReferralRequest>,
+    ) -> Result<Versioned<ServiceRequestResponse>, ErrorReply> {
+        tracing::info!("Updating referral: {}", id);
+
+        let expected_version = if_match_version(&headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the referral's ETag in If-Match to update it".to_string(),
+                }),
+            )
+        })?;
+
+        let context = "Failed to update referral";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        let updated = state.service.update(id, request, &expected_version).await;
+        Self::changed(&state, &auth, id, updated, "Referral updated", context).await
+    }
+
+    /// Delete referral
+    pub async fn delete_referral(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Deleting referral: {}", id);
+
+        let context = "Failed to delete referral";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.delete(id).await {
+            Ok(true) => {
+                let event = (AuditEventType::Delete, AuditAction::Delete);
+                Self::audit(&state, &auth, event, &current, "Referral deleted", context).await?;
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Accept a referral
+    pub async fn accept_referral(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        request: Option<Json<AcceptRequest>>,
+    ) -> Result<Versioned<ServiceRequestResponse>, ErrorReply> {
+        tracing::info!("Accepting referral: {}", id);
+
+        let context = "Failed to accept referral";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        let Json(request) = request.unwrap_or_default();
+        let accepted = state.service.accept(id, request.note).await;
+        Self::changed(&state, &auth, id, accepted, "Referral accepted", context).await
+    }
+
+    /// Decline a referral, which revokes it
+    pub async fn decline_referral(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<DeclineRequest>,
+    ) -> Result<Versioned<ServiceRequestResponse>, ErrorReply> {
+        tracing::info!("Declining referral: {}", id);
+
+        let context = "Failed to decline referral";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        let declined = state.service.decline(id, request.reason).await;
+        Self::changed(&state, &auth, id, declined, "Referral declined", context).await
+    }
+
+    /// Link the appointment booked for an accepted referral
+    pub async fn link_appointment(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path((id, appointment_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<Versioned<ServiceRequestResponse>, ErrorReply> {
+        tracing::info!("Linking appointment {} to referral {}", appointment_id, id);
+
+        let context = "Failed to link appointment";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        let linked = state.service.link_appointment(id, appointment_id).await;
+        Self::changed(&state, &auth, id, linked, "Appointment linked to referral", context).await
+    }
+
+    /// The referral letter, with the patient's current problems, as a PDF
+    pub async fn letter_pdf(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<([(HeaderName, &'static str); 1], Vec<u8>), ErrorReply> {
+        let context = "Failed to generate referral letter";
+        let referral = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Read, referral.patient_id, context).await?;
+        match state.service.letter_pdf(id).await {
+            Ok(Some(pdf)) => {
+                let event = (AuditEventType::Export, AuditAction::Read);
+                Self::audit(&state, &auth, event, &referral, "Referral letter generated", context).await?;
+                Ok(([(header::CONTENT_TYPE, "application/pdf")], pdf))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// The referral as a C-CDA Referral Note
+    pub async fn letter_ccda(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<([(HeaderName, &'static str); 1], String), ErrorReply> {
+        let context = "Failed to generate referral note";
+        let referral = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Read, referral.patient_id, context).await?;
+        match state.service.letter_ccda(id).await {
+            Ok(Some(document)) => {
+                let event = (AuditEventType::Export, AuditAction::Read);
+                Self::audit(&state, &auth, event, &referral, "Referral note generated", context).await?;
+                Ok(([(header::CONTENT_TYPE, "application/xml")], document))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Receive a referral as a FHIR ServiceRequest. A referral received
+    /// before is answered with the one filed then, 200 rather than 201.
+    pub async fn receive_fhir(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        Json(resource): Json<serde_json::Value>,
+    ) -> Result<(StatusCode, Versioned<ServiceRequestResponse>), ErrorReply> {
+        let context = "Failed to receive referral";
+        Self::require_create(&state, &auth, context).await?;
+        let inbound = parse_fhir(resource).map_err(|message| {
+            Self::error_response("Invalid ServiceRequest", HimsError::ValidationError { message })
+        })?;
+
+        match state.service.receive(inbound).await {
+            Ok((referral, created)) => {
+                tracing::info!("Received referral {} for patient {}", referral.id, referral.patient_id);
+                let event = (AuditEventType::Create, AuditAction::Create);
+                Self::audit(&state, &auth, event, &referral, "Referral received", context).await?;
+                let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+                Ok((status, versioned(&referral.meta.clone(), Self::referral_to_response(referral))))
+            }
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Receive a referral as an HL7 v2 REF^I12 message and acknowledge it:
+    /// AA once filed (or filed before), AE when it cannot be filed, e.g. for
+    /// an unknown patient, and AR when it cannot be read
+    pub async fn receive_hl7(
+        State(state): State<ReferralState>,
+        auth: AuthContext,
+        body: String,
+    ) -> Result<([(HeaderName, &'static str); 1], String), ErrorReply> {
+        let context = "Failed to receive referral";
+        Self::require_create(&state, &auth, context).await?;
+        let (control_id, ack_code) = match parse_hl7(&body) {
+            Ok((control_id, inbound)) => match state.service.receive(inbound).await {
+                Ok((referral, _)) => {
+                    tracing::info!("Received referral {} for patient {}", referral.id, referral.patient_id);
+                    let event = (AuditEventType::Create, AuditAction::Create);
+                    Self::audit(&state, &auth, event, &referral, "Referral received", context).await?;
+                    (control_id, "AA")
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to file HL7 referral {}: {}", control_id, e);
+                    (control_id, "AE")
+                }
+            },
+            Err(message) => {
+                tracing::warn!("Rejected HL7 referral: {}", message);
+                (String::new(), "AR")
+            }
+        };
+
+        match Hl7Generator::generate_ack_message(&control_id, ack_code) {
+            Ok(ack) => Ok(([(header::CONTENT_TYPE, "application/hl7-v2")], ack)),
+            Err(e) => Err(Self::error_response("Failed to acknowledge referral", e)),
+        }
+    }
+
+    /// The referral, or 404
+    async fn existing(state: &ReferralState, id: Uuid, context: &str) -> Result<Referral, ErrorReply> {
+        match state.service.get(id).await {
+            Ok(Some(referral)) => Ok(referral),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// The changed referral as served, its change audited
+    async fn changed(
+        state: &ReferralState,
+        auth: &AuthContext,
+        id: Uuid,
+        result: Result<Option<Referral>, HimsError>,
+        details: &str,
+        context: &str,
+    ) -> Result<Versioned<ServiceRequestResponse>, ErrorReply> {
+        match result {
+            Ok(Some(referral)) => {
+                let event = (AuditEventType::Update, AuditAction::Update);
+                Self::audit(state, auth, event, &referral, details, context).await?;
+                Ok(versioned(&referral.meta.clone(), Self::referral_to_response(referral)))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Fail with 403 unless the user may take `action` on the patient
+    async fn authorize(
+        state: &ReferralState,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        action: Action,
+        patient_id: Uuid,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), auth, headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        authorizer
+            .authorize(action, Resource::Patient(patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// Fail with 403 unless the user's roles grant filing new records
+    async fn require_create(state: &ReferralState, auth: &AuthContext, context: &str) -> Result<(), ErrorReply> {
+        let permissions = state
+            .role_service
+            .get_user_permissions(auth.user_id)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        if permissions.contains(&Action::Create) {
+            return Ok(());
+        }
+        tracing::warn!("User {} lacks {} permission", auth.user_id, Action::Create);
+        Err(Self::error_response(
+            context,
+            HimsError::SecurityError {
+                message: format!("Missing required permission: {}", Action::Create),
+            },
+        ))
+    }
+
+    /// Record the user's access to or change of a referral
+    async fn audit(
+        state: &ReferralState,
+        auth: &AuthContext,
+        (event_type, action): (AuditEventType, AuditAction),
+        referral: &Referral,
+        details: &str,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let audit_log = auth
+            .audit(AuditLog::new(event_type, action, SERVICE_REQUEST_RESOURCE_TYPE.to_string()))
+            .with_patient(referral.patient_id)
+            .with_resource(referral.id)
+            .with_details(details.to_string());
+        state
+            .audit_service
+            .create_audit_log(&audit_log)
+            .await
+            .map(|_| ())
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// Convert Referral model to FHIR response format
+    pub fn referral_to_response(referral: Referral) -> ServiceRequestResponse {
+        let practitioner = |id: Uuid| format!("Practitioner/{}", id);
+
+        let mut extension = vec![ServiceRequestExtension {
+            url: REFERRAL_ACCEPTANCE_EXTENSION.to_string(),
+            value_code: Some(referral.acceptance.as_str().to_string()),
+            value_reference: None,
+        }];
+        if let Some(appointment_id) = referral.appointment_id {
+            extension.push(ServiceRequestExtension {
+                url: REFERRAL_APPOINTMENT_EXTENSION.to_string(),
+                value_code: None,
+                value_reference: Some(Reference {
+                    reference: format!("Appointment/{}", appointment_id),
+                    display: None,
+                }),
+            });
+        }
+
+        let requester = (referral.requester_id.is_some() || referral.requester_display.is_some()).then(|| {
+            DisplayReference {
+                reference: referral.requester_id.map(practitioner),
+                display: referral.requester_display.clone(),
+            }
+        });
+        let performer = (referral.performer_id.is_some() || referral.performer_display.is_some()).then(|| {
+            DisplayReference {
+                reference: referral.performer_id.map(practitioner),
+                display: referral.performer_display.clone(),
+            }
+        });
+        let mut note: Vec<Annotation> = referral.note.into_iter().map(|text| Annotation { text }).collect();
+        note.extend(referral.response_note.map(|text| Annotation { text }));
+
+        ServiceRequestResponse {
+            resource_type: SERVICE_REQUEST_RESOURCE_TYPE.to_string(),
+            id: referral.id,
+            meta: referral.meta,
+            extension,
+            identifier: referral
+                .identifier
+                .map(|value| Identifier {
+                    use_type: None,
+                    system: None,
+                    value,
+                })
+                .into_iter()
+                .collect(),
+            status: referral.status.as_str().to_string(),
+            intent: "order".to_string(),
+            category: vec![CodeableConcept {
+                coding: vec![Coding {
+                    system: Some(SNOMED_CT_SYSTEM.to_string()),
+                    version: None,
+                    code: Some(PATIENT_REFERRAL_CODE.to_string()),
+                    display: Some("Patient referral".to_string()),
+                }],
+                text: None,
+            }],
+            priority: referral.priority.as_str().to_string(),
+            code: referral.code,
+            subject: Reference {
+                reference: format!("Patient/{}", referral.patient_id),
+                display: None,
+            },
+            authored_on: referral.authored_on,
+            requester,
+            performer_type: referral.specialty.into_iter().collect(),
+            performer: performer.into_iter().collect(),
+            reason_code: referral.reason_code,
+            reason_reference: referral
+                .reason_condition_ids
+                .iter()
+                .map(|id| Reference {
+                    reference: format!("Condition/{}", id),
+                    display: None,
+                })
+                .collect(),
+            note,
+        }
+    }
+
+    /// Convert a page of search results to a FHIR searchset Bundle
+    fn referrals_to_bundle(result: ReferralSearchResult, link: Vec<BundleLink>) -> ServiceRequestBundle {
+        let search = EntrySearch {
+            mode: SearchEntryMode::Match,
+        };
+        let entry = result
+            .referrals
+            .into_iter()
+            .map(|referral| ServiceRequestBundleEntry {
+                full_url: format!("ServiceRequest/{}", referral.id),
+                resource: Self::referral_to_response(referral),
+                search: Some(search.clone()),
+            })
+            .collect();
+
+        ServiceRequestBundle {
+            resource_type: "Bundle".to_string(),
+            id: Uuid::new_v4(),
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: Utc::now(),
+                profile: vec!["http://hl7.org/fhir/StructureDefinition/Bundle".to_string()],
+                security: vec![],
+                tag: vec![],
+            },
+            bundle_type: "searchset".to_string(),
+            total: result.total,
+            link,
+            entry,
+        }
+    }
+
+    fn not_found(id: Uuid) -> ErrorReply {
+        tracing::warn!("Referral not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Referral not found".to_string(),
+                message: format!("Referral with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => precondition_status(&e).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
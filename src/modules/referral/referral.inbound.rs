@@ -0,0 +1,360 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::constants::{ICD_10_SYSTEM, SERVICE_REQUEST_RESOURCE_TYPE, SNOMED_CT_SYSTEM};
+use crate::models::{CodeableConcept, Coding, Referral, ReferralDirection, ReferralStatus, TaskPriority};
+use crate::standards::hl7v2::parser::{Hl7Parser, Hl7Segment};
+
+/// How an inbound referral names its patient
+#[derive(Debug, Clone, PartialEq)]
+pub enum InboundPatient {
+    /// `Patient/{id}` on this server
+    Id(Uuid),
+    /// An identifier value (MRN) the sender used
+    Identifier(String),
+}
+
+/// A referral received from another organization, before it is filed
+/// against the patient it names
+#[derive(Debug, Clone)]
+pub struct InboundReferral {
+    pub patient: InboundPatient,
+    /// The referral, active and pending acceptance; its patient is set when
+    /// it is filed
+    pub referral: Referral,
+}
+
+fn inbound() -> Referral {
+    let mut referral = Referral::new(Uuid::nil(), ReferralDirection::Inbound);
+    referral.status = ReferralStatus::Active;
+    referral
+}
+
+fn field(segment: &Hl7Segment, index: usize) -> &str {
+    segment.fields.get(index).map(String::as_str).unwrap_or_default()
+}
+
+fn component(value: &str, index: usize) -> &str {
+    value.split('^').nth(index).unwrap_or_default()
+}
+
+/// Concept of an HL7 coded element (code, text, coding system)
+fn coded(value: &str) -> Option<CodeableConcept> {
+    let code = component(value, 0);
+    let text = component(value, 1);
+    if code.is_empty() && text.is_empty() {
+        return None;
+    }
+    let system = match component(value, 2) {
+        "I10" | "ICD10" | "I10C" => Some(ICD_10_SYSTEM.to_string()),
+        "SCT" | "SNM" => Some(SNOMED_CT_SYSTEM.to_string()),
+        _ => None,
+    };
+    let coding = (!code.is_empty())
+        .then(|| Coding {
+            system,
+            version: None,
+            code: Some(code.to_string()),
+            display: (!text.is_empty()).then(|| text.to_string()),
+        })
+        .into_iter()
+        .collect();
+    Some(CodeableConcept {
+        coding,
+        text: (!text.is_empty()).then(|| text.to_string()),
+    })
+}
+
+/// A person's name (XPN: family, given, middle, suffix, prefix) as written
+fn person_name(value: &str) -> Option<String> {
+    let name: Vec<&str> = [4, 1, 2, 0, 3]
+        .iter()
+        .map(|&index| component(value, index))
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!name.is_empty()).then(|| name.join(" "))
+}
+
+/// HL7 TS (`YYYYMMDD[HHMM[SS]]`, offset ignored) as UTC
+fn hl7_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+    match digits.len() {
+        8 => NaiveDate::parse_from_str(&digits, "%Y%m%d").ok()?.and_hms_opt(0, 0, 0),
+        12 => NaiveDateTime::parse_from_str(&digits, "%Y%m%d%H%M").ok(),
+        14.. => NaiveDateTime::parse_from_str(&digits[..14], "%Y%m%d%H%M%S").ok(),
+        _ => None,
+    }
+    .map(|at| at.and_utc())
+}
+
+/// Read an HL7 v2 REF^I12 patient referral: RF1 for the referral, PRD for
+/// the referring (RP) and referred-to (RT, CP) providers, PID-3 for the
+/// patient, DG1 diagnoses and RF1-10 as reasons, and NTE as the note. A
+/// referral with no referring provider is credited to the sending facility.
+/// Returns the message's control ID (MSH-10) with the referral.
+pub fn parse_hl7(message: &str) -> Result<(String, InboundReferral), String> {
+    let parsed = Hl7Parser::new().parse_message(message).map_err(|e| e.to_string())?;
+    let mut message_type = parsed.message_type.split('^');
+    if (message_type.next(), message_type.next()) != (Some("REF"), Some("I12")) {
+        return Err(format!("{} is not a patient referral (REF^I12)", parsed.message_type));
+    }
+
+    let mut referral = inbound();
+    let mut control_id = String::new();
+    let mut sending_facility = None;
+    let mut patient_identifier = String::new();
+    let mut has_rf1 = false;
+    let mut notes = Vec::new();
+
+    for segment in &parsed.segments {
+        match segment.segment_type.as_str() {
+            // MSH fields are numbered from the field separator
+            "MSH" => {
+                sending_facility = Some(component(field(segment, 3), 0).to_string()).filter(|name| !name.is_empty());
+                control_id = field(segment, 9).to_string();
+            }
+            "RF1" => {
+                has_rf1 = true;
+                referral.priority = match component(field(segment, 1), 0) {
+                    "S" => TaskPriority::Stat,
+                    "A" => TaskPriority::Asap,
+                    _ => TaskPriority::Routine,
+                };
+                referral.code = coded(field(segment, 2));
+                referral.identifier = Some(component(field(segment, 5), 0).to_string()).filter(|id| !id.is_empty());
+                if let Some(effective) = hl7_timestamp(field(segment, 6)) {
+                    referral.authored_on = effective;
+                }
+                referral.reason_code.extend(field(segment, 9).split('~').filter_map(coded));
+            }
+            "PRD" => {
+                let name = person_name(field(segment, 1));
+                match component(field(segment, 0), 0) {
+                    "RP" => referral.requester_display = name,
+                    "RT" | "CP" if referral.performer_display.is_none() => referral.performer_display = name,
+                    _ => {}
+                }
+            }
+            // PID-3, first repetition, ID component
+            "PID" => {
+                patient_identifier = component(field(segment, 2).split('~').next().unwrap_or_default(), 0).to_string();
+            }
+            "DG1" => referral.reason_code.extend(coded(field(segment, 2))),
+            "NTE" => notes.push(field(segment, 2).to_string()),
+            _ => {}
+        }
+    }
+
+    if !has_rf1 {
+        return Err("Referral has no RF1 segment".to_string());
+    }
+    if patient_identifier.is_empty() {
+        return Err("Referral has no patient identifier in PID-3".to_string());
+    }
+    if referral.requester_display.is_none() {
+        referral.requester_display = sending_facility;
+    }
+    notes.retain(|note| !note.is_empty());
+    referral.note = (!notes.is_empty()).then(|| notes.join("\n"));
+
+    Ok((
+        control_id,
+        InboundReferral {
+            patient: InboundPatient::Identifier(patient_identifier),
+            referral,
+        },
+    ))
+}
+
+/// CodeableConcept as FHIR JSON has it, where `coding` may be left out
+#[derive(Debug, Deserialize)]
+struct Concept {
+    #[serde(default)]
+    coding: Vec<Coding>,
+    text: Option<String>,
+}
+
+impl From<Concept> for CodeableConcept {
+    fn from(concept: Concept) -> Self {
+        CodeableConcept {
+            coding: concept.coding,
+            text: concept.text,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ReferenceElement {
+    reference: Option<String>,
+    identifier: Option<IdentifierElement>,
+    display: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentifierElement {
+    value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnnotationElement {
+    text: String,
+}
+
+/// The parts of a FHIR R4 ServiceRequest a received referral keeps
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceRequestResource {
+    resource_type: String,
+    #[serde(default)]
+    identifier: Vec<IdentifierElement>,
+    priority: Option<String>,
+    code: Option<Concept>,
+    subject: ReferenceElement,
+    authored_on: Option<String>,
+    requester: Option<ReferenceElement>,
+    performer_type: Option<Concept>,
+    #[serde(default)]
+    performer: Vec<ReferenceElement>,
+    #[serde(default)]
+    reason_code: Vec<Concept>,
+    #[serde(default)]
+    note: Vec<AnnotationElement>,
+}
+
+/// Read a FHIR R4 ServiceRequest sent by another organization. The subject
+/// is `Patient/{id}` on this server or carries an identifier; the
+/// requester's and performer's display names are kept, as their references
+/// point elsewhere.
+pub fn parse_fhir(resource: Value) -> Result<InboundReferral, String> {
+    let request: ServiceRequestResource =
+        serde_json::from_value(resource).map_err(|e| format!("Invalid ServiceRequest: {}", e))?;
+    if request.resource_type != SERVICE_REQUEST_RESOURCE_TYPE {
+        return Err(format!("Expected a ServiceRequest, got a {}", request.resource_type));
+    }
+
+    let patient = match (&request.subject.reference, &request.subject.identifier) {
+        (Some(reference), _) => {
+            let id = reference
+                .strip_prefix("Patient/")
+                .and_then(|id| Uuid::parse_str(id).ok())
+                .ok_or_else(|| format!("Subject {} is not a patient on this server", reference))?;
+            InboundPatient::Id(id)
+        }
+        (None, Some(IdentifierElement { value: Some(value) })) if !value.is_empty() => {
+            InboundPatient::Identifier(value.clone())
+        }
+        _ => return Err("Subject names no patient".to_string()),
+    };
+
+    let mut referral = inbound();
+    if let Some(priority) = request.priority {
+        referral.priority = TaskPriority::from_string(&priority);
+        if referral.priority.as_str() != priority {
+            return Err(format!("Unknown priority {}", priority));
+        }
+    }
+    if let Some(authored_on) = request.authored_on {
+        referral.authored_on = DateTime::parse_from_rfc3339(&authored_on)
+            .map(|at| at.with_timezone(&Utc))
+            .or_else(|_| NaiveDate::parse_from_str(&authored_on, "%Y-%m-%d").map(|date| date.and_time(Default::default()).and_utc()))
+            .map_err(|_| format!("Invalid authoredOn {}", authored_on))?;
+    }
+    referral.identifier = request.identifier.into_iter().find_map(|identifier| identifier.value);
+    referral.code = request.code.map(Into::into);
+    referral.specialty = request.performer_type.map(Into::into);
+    referral.reason_code = request.reason_code.into_iter().map(Into::into).collect();
+    referral.requester_display = request.requester.unwrap_or_default().display;
+    referral.performer_display = request.performer.into_iter().find_map(|performer| performer.display);
+    let notes: Vec<String> = request.note.into_iter().map(|note| note.text).collect();
+    referral.note = (!notes.is_empty()).then(|| notes.join("\n"));
+
+    Ok(InboundReferral { patient, referral })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const REFERRAL: &str = "MSH|^~\\&|GP|NORTHSIDE|HIMS|MAIN|20240105093000||REF^I12^REF_I12|MSG0001|P|2.5\r\
+        RF1|P^Pending|A^ASAP|Med^Medical|||R-884|20240105\r\
+        PRD|RP|Patel^Anil^^^Dr\r\
+        PRD|RT|Okafor^Ngozi^^^Dr\r\
+        PID|1||MRN-42^^^MAIN~99||Doe^Jane\r\
+        DG1|1||I20.9^Angina pectoris^I10\r\
+        NTE|1||Exertional chest pain for two weeks\r";
+
+    #[test]
+    fn test_parse_hl7() {
+        let (control_id, inbound) = parse_hl7(REFERRAL).unwrap();
+        assert_eq!(control_id, "MSG0001");
+        assert_eq!(inbound.patient, InboundPatient::Identifier("MRN-42".to_string()));
+
+        let referral = inbound.referral;
+        assert_eq!(referral.direction, ReferralDirection::Inbound);
+        assert_eq!(referral.status, ReferralStatus::Active);
+        assert_eq!(referral.priority, TaskPriority::Asap);
+        assert_eq!(referral.identifier.as_deref(), Some("R-884"));
+        assert_eq!(referral.requester_display.as_deref(), Some("Dr Anil Patel"));
+        assert_eq!(referral.performer_display.as_deref(), Some("Dr Ngozi Okafor"));
+        assert_eq!(referral.authored_on.format("%Y-%m-%d").to_string(), "2024-01-05");
+        assert_eq!(referral.reason_code.len(), 1);
+        assert_eq!(referral.reason_code[0].coding[0].system.as_deref(), Some(ICD_10_SYSTEM));
+        assert_eq!(referral.note.as_deref(), Some("Exertional chest pain for two weeks"));
+
+        // No referring provider: the sending facility referred the patient
+        let unsigned = REFERRAL.replace("PRD|RP|Patel^Anil^^^Dr\r", "");
+        assert_eq!(parse_hl7(&unsigned).unwrap().1.referral.requester_display.as_deref(), Some("NORTHSIDE"));
+
+        assert!(parse_hl7(&REFERRAL.replace("REF^I12", "ADT^A04")).is_err());
+        assert!(parse_hl7(&REFERRAL.replace("MRN-42^^^MAIN~99", "")).is_err());
+    }
+
+    #[test]
+    fn test_parse_fhir() {
+        let patient_id = Uuid::new_v4();
+        let inbound = parse_fhir(json!({
+            "resourceType": "ServiceRequest",
+            "identifier": [{ "system": "urn:oid:1.2.3", "value": "SR-17" }],
+            "status": "active",
+            "intent": "order",
+            "priority": "urgent",
+            "subject": { "reference": format!("Patient/{}", patient_id) },
+            "authoredOn": "2024-03-01",
+            "requester": { "reference": "Practitioner/abc", "display": "Dr Mensah" },
+            "performerType": { "text": "Dermatology" },
+            "reasonCode": [{ "text": "Changing naevus" }],
+            "note": [{ "text": "Photos attached" }]
+        }))
+        .unwrap();
+        assert_eq!(inbound.patient, InboundPatient::Id(patient_id));
+        let referral = inbound.referral;
+        assert_eq!(referral.priority, TaskPriority::Urgent);
+        assert_eq!(referral.identifier.as_deref(), Some("SR-17"));
+        assert_eq!(referral.requester_display.as_deref(), Some("Dr Mensah"));
+        assert_eq!(referral.recipient().as_deref(), Some("Dermatology"));
+        assert_eq!(referral.reason_code[0].text.as_deref(), Some("Changing naevus"));
+
+        let by_identifier = parse_fhir(json!({
+            "resourceType": "ServiceRequest",
+            "subject": { "identifier": { "value": "MRN-42" } }
+        }))
+        .unwrap();
+        assert_eq!(by_identifier.patient, InboundPatient::Identifier("MRN-42".to_string()));
+
+        assert!(parse_fhir(json!({ "resourceType": "Task", "subject": { "reference": "Patient/1" } })).is_err());
+        assert!(parse_fhir(json!({
+            "resourceType": "ServiceRequest",
+            "subject": { "reference": "Group/1" }
+        }))
+        .is_err());
+        assert!(parse_fhir(json!({
+            "resourceType": "ServiceRequest",
+            "priority": "whenever",
+            "subject": { "reference": format!("Patient/{}", patient_id) }
+        }))
+        .is_err());
+    }
+}
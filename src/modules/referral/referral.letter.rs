@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+
+use crate::exporters::pdf::pdf::PdfDocument;
+use crate::models::{CodeableConcept, Condition, Patient, Referral};
+
+/// The patient's name as a letter gives it
+fn patient_name(patient: &Patient) -> String {
+    patient
+        .name
+        .first()
+        .map(|name| match &name.text {
+            Some(text) => text.clone(),
+            None => name.given.iter().chain(name.family.iter()).cloned().collect::<Vec<_>>().join(" "),
+        })
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "Unnamed patient".to_string())
+}
+
+/// A referral letter: who the patient is, why they are referred and how
+/// soon, their current problems, and any note from the referrer. Conditions
+/// given as reasons are listed among the reasons.
+pub fn referral_letter(referral: &Referral, patient: &Patient, problems: &[Condition], now: DateTime<Utc>) -> PdfDocument {
+    let mut letter = PdfDocument::new(format!("Referral letter for {}", patient_name(patient)));
+    letter.heading("Referral letter");
+    letter.paragraph(format!("Date: {}", now.format("%d %B %Y")));
+    letter.paragraph(format!(
+        "To: {}",
+        referral.recipient().unwrap_or_else(|| "The receiving clinician".to_string())
+    ));
+    if let Some(requester) = &referral.requester_display {
+        letter.paragraph(format!("From: {}", requester));
+    }
+    letter.space();
+
+    letter.heading("Patient");
+    letter.paragraph(format!("Name: {}", patient_name(patient)));
+    if let Some(birth_date) = patient.birth_date {
+        letter.paragraph(format!("Date of birth: {}", birth_date.format("%d %B %Y")));
+    }
+    for identifier in &patient.identifier {
+        letter.paragraph(format!("Identifier: {}", identifier.value));
+    }
+    letter.space();
+
+    letter.heading("Reason for referral");
+    let mut reasons: Vec<String> = referral.reason_code.iter().filter_map(CodeableConcept::display_text).collect();
+    reasons.extend(
+        problems
+            .iter()
+            .filter(|condition| referral.reason_condition_ids.contains(&condition.id))
+            .filter_map(|condition| condition.code.display_text()),
+    );
+    if reasons.is_empty() {
+        reasons.push("Not stated".to_string());
+    }
+    for reason in reasons {
+        letter.paragraph(format!("- {}", reason));
+    }
+    if let Some(requested) = referral.code.as_ref().and_then(CodeableConcept::display_text) {
+        letter.paragraph(format!("Requested: {}", requested));
+    }
+    letter.paragraph(format!("Priority: {}", referral.priority.as_str()));
+    letter.space();
+
+    if !problems.is_empty() {
+        letter.heading("Current problems");
+        for condition in problems {
+            let onset = condition.onset.map(|onset| format!(" (since {})", onset.format("%Y-%m-%d"))).unwrap_or_default();
+            letter.paragraph(format!("- {}{}", condition.code.display_text().unwrap_or_default(), onset));
+        }
+        letter.space();
+    }
+
+    if let Some(note) = &referral.note {
+        letter.heading("Notes");
+        letter.paragraph(note.clone());
+        letter.space();
+    }
+    letter.paragraph(format!("Referral reference: {}", referral.id));
+    letter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Gender, HumanName, ReferralDirection};
+
+    #[test]
+    fn test_referral_letter() {
+        let patient = Patient::new(
+            vec![HumanName {
+                use_type: None,
+                text: None,
+                family: Some("Doe".to_string()),
+                given: vec!["Jane".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            }],
+            vec![],
+            Gender::Female,
+            chrono::NaiveDate::from_ymd_opt(1970, 6, 1),
+        );
+        let mut referral = Referral::new(patient.id, ReferralDirection::Outbound);
+        referral.specialty = Some(CodeableConcept {
+            coding: vec![],
+            text: Some("Cardiology".to_string()),
+        });
+        referral.reason_code.push(CodeableConcept {
+            coding: vec![],
+            text: Some("Chest pain (exertional)".to_string()),
+        });
+
+        let pdf = referral_letter(&referral, &patient, &[], Utc::now()).render();
+        let contains = |needle: &str| pdf.windows(needle.len()).any(|window| window == needle.as_bytes());
+        assert!(contains("(To: Cardiology) Tj"));
+        assert!(contains("(Name: Jane Doe) Tj"));
+        assert!(contains("(- Chest pain \\(exertional\\)) Tj"));
+        assert!(contains("/Title (Referral letter for Jane Doe)"));
+    }
+}
@@ -0,0 +1,804 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::exporters::CcdaReferralNote;
+use crate::models::constants::PART2_REDISCLOSURE_NOTICE;
+use crate::models::{
+    CodeableConcept, Patient, Referral, ReferralAcceptance, ReferralDirection, ReferralStatus, TaskPriority,
+};
+use crate::modules::authorization::PurposeOfUse;
+use crate::modules::condition::ConditionService;
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::modules::part2::SegmentedConditions;
+use crate::modules::patient::PatientService;
+use crate::modules::referral::referral_inbound::{InboundPatient, InboundReferral};
+use crate::modules::referral::referral_letter::referral_letter;
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{reference_id, split_modifier, SearchParamDefinition, TotalMode};
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
+
+// Import SQL queries from separate file
+use crate::modules::referral::referral_sql::*;
+
+/// Referral from a row of any query selecting its columns
+fn referral_from_row(row: &PgRow) -> Referral {
+    let concept = |column: &str| {
+        row.get::<Option<serde_json::Value>, _>(column)
+            .and_then(|value| serde_json::from_value(value).ok())
+    };
+    Referral {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        direction: ReferralDirection::from_string(row.get("direction")).unwrap_or_default(),
+        status: ReferralStatus::from_string(row.get("status")),
+        priority: TaskPriority::from_string(row.get("priority")),
+        identifier: row.get("identifier"),
+        code: concept("code"),
+        specialty: concept("specialty"),
+        reason_code: serde_json::from_value(row.get("reason_code")).unwrap_or_default(),
+        reason_condition_ids: row.get("reason_condition_ids"),
+        requester_id: row.get("requester_id"),
+        requester_display: row.get("requester_display"),
+        performer_id: row.get("performer_id"),
+        performer_display: row.get("performer_display"),
+        acceptance: ReferralAcceptance::from_string(row.get("acceptance")),
+        responded_at: row.get("responded_at"),
+        response_note: row.get("response_note"),
+        appointment_id: row.get("appointment_id"),
+        authored_on: row.get("authored_on"),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
+    }
+}
+
+/// Check a referral before it is saved: an outbound referral says where
+/// the patient is sent and why. Inbound referrals are taken as sent.
+pub fn check_referral(referral: &Referral) -> Result<(), HimsError> {
+    let invalid = |message: &str| HimsError::ValidationError { message: message.to_string() };
+
+    if referral.direction == ReferralDirection::Inbound {
+        return Ok(());
+    }
+    if referral.specialty.is_none() && referral.performer_id.is_none() && referral.performer_display.is_none() {
+        return Err(invalid("A referral needs a specialty or a clinician to refer the patient to"));
+    }
+    if referral.reason_code.is_empty() && referral.reason_condition_ids.is_empty() {
+        return Err(invalid("A referral needs a reason, coded or as the conditions it is for"));
+    }
+    Ok(())
+}
+
+/// An outbound referral as clients send it to create or replace one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferralRequest {
+    pub patient_id: Uuid,
+    #[serde(default)]
+    pub status: ReferralStatus,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    pub code: Option<CodeableConcept>,
+    pub specialty: Option<CodeableConcept>,
+    #[serde(default)]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(default)]
+    pub reason_condition_ids: Vec<Uuid>,
+    pub requester_id: Option<Uuid>,
+    pub requester_display: Option<String>,
+    pub performer_id: Option<Uuid>,
+    pub performer_display: Option<String>,
+    pub note: Option<String>,
+}
+
+impl From<ReferralRequest> for Referral {
+    fn from(request: ReferralRequest) -> Self {
+        let mut referral = Referral::new(request.patient_id, ReferralDirection::Outbound);
+        referral.status = request.status;
+        referral.priority = request.priority;
+        referral.code = request.code;
+        referral.specialty = request.specialty;
+        referral.reason_code = request.reason_code;
+        referral.reason_condition_ids = request.reason_condition_ids;
+        referral.requester_id = request.requester_id;
+        referral.requester_display = request.requester_display;
+        referral.performer_id = request.performer_id;
+        referral.performer_display = request.performer_display;
+        referral.note = request.note;
+        referral
+    }
+}
+
+/// Page size of a referral search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 50;
+
+/// Largest page a referral search returns
+pub const MAX_SEARCH_COUNT: i64 = 200;
+
+/// Paging of referral searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Order of referral searches, most recently authored first, as
+/// SEARCH_REFERRALS keys its rows
+const SEARCH_KEYS: [SortKey; 1] = [SortKey::new("authored_on", "timestamptz", true)];
+
+/// Parsed referral search
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferralSearch {
+    pub patient: Vec<Uuid>,
+    /// Users who made the referrals
+    pub requester: Vec<Uuid>,
+    /// Users the patients are referred to
+    pub performer: Vec<Uuid>,
+    /// Status codes; any matches
+    pub status: Vec<String>,
+    /// Priority codes; any matches
+    pub priority: Vec<String>,
+    pub direction: Option<ReferralDirection>,
+    /// Acceptance codes; any matches
+    pub acceptance: Vec<String>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for ReferralSearch {
+    fn default() -> Self {
+        Self {
+            patient: Vec::new(),
+            requester: Vec::new(),
+            performer: Vec::new(),
+            status: Vec::new(),
+            priority: Vec::new(),
+            direction: None,
+            acceptance: Vec::new(),
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// A page of referral search results
+#[derive(Debug)]
+pub struct ReferralSearchResult {
+    pub referrals: Vec<Referral>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl ReferralSearch {
+    /// Search parameters `from_params` supports, for the CapabilityStatement
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "patient",
+            param_type: "reference",
+            documentation: "The patient referred",
+        },
+        SearchParamDefinition {
+            name: "requester",
+            param_type: "reference",
+            documentation: "The user who made the referral",
+        },
+        SearchParamDefinition {
+            name: "performer",
+            param_type: "reference",
+            documentation: "The user the patient is referred to",
+        },
+        SearchParamDefinition {
+            name: "status",
+            param_type: "token",
+            documentation: "Request status, e.g. active; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "priority",
+            param_type: "token",
+            documentation: "routine, urgent, asap or stat; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "direction",
+            param_type: "token",
+            documentation: "outbound for referrals made here, inbound for those received",
+        },
+        SearchParamDefinition {
+            name: "acceptance",
+            param_type: "token",
+            documentation: "pending, accepted or declined; comma-separated values match any",
+        },
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored; invalid
+    /// values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+
+        for (param, value) in params {
+            let (name, modifier) = split_modifier(param);
+            match (name, modifier) {
+                ("patient", None | Some("Patient")) => {
+                    for reference in value.split(',') {
+                        search.patient.push(reference_id(reference, "Patient")?);
+                    }
+                }
+                ("requester", None | Some("Practitioner")) => {
+                    for reference in value.split(',') {
+                        search.requester.push(reference_id(reference, "Practitioner")?);
+                    }
+                }
+                ("performer", None | Some("Practitioner")) => {
+                    for reference in value.split(',') {
+                        search.performer.push(reference_id(reference, "Practitioner")?);
+                    }
+                }
+                ("status", None) => {
+                    for code in value.split(',') {
+                        if ReferralStatus::from_string(code).as_str() != code {
+                            return Err(invalid(name, code));
+                        }
+                        search.status.push(code.to_string());
+                    }
+                }
+                ("priority", None) => {
+                    for code in value.split(',') {
+                        if TaskPriority::from_string(code).as_str() != code {
+                            return Err(invalid(name, code));
+                        }
+                        search.priority.push(code.to_string());
+                    }
+                }
+                ("direction", None) => {
+                    search.direction = Some(ReferralDirection::from_string(value).ok_or_else(|| invalid(name, value))?);
+                }
+                ("acceptance", None) => {
+                    for code in value.split(',') {
+                        if ReferralAcceptance::from_string(code).as_str() != code {
+                            return Err(invalid(name, code));
+                        }
+                        search.acceptance.push(code.to_string());
+                    }
+                }
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported referral search parameter {}", param),
+            }
+        }
+        search.page.check_cursor(&SEARCH_KEYS)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on referrals for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if !self.patient.is_empty() {
+            query.push(" AND patient_id = ANY(").push_bind(self.patient.clone()).push(")");
+        }
+        if !self.requester.is_empty() {
+            query.push(" AND requester_id = ANY(").push_bind(self.requester.clone()).push(")");
+        }
+        if !self.performer.is_empty() {
+            query.push(" AND performer_id = ANY(").push_bind(self.performer.clone()).push(")");
+        }
+        if !self.status.is_empty() {
+            query.push(" AND status = ANY(").push_bind(self.status.clone()).push(")");
+        }
+        if !self.priority.is_empty() {
+            query.push(" AND priority = ANY(").push_bind(self.priority.clone()).push(")");
+        }
+        if let Some(direction) = self.direction {
+            query.push(" AND direction = ").push_bind(direction.as_str());
+        }
+        if !self.acceptance.is_empty() {
+            query.push(" AND acceptance = ANY(").push_bind(self.acceptance.clone()).push(")");
+        }
+    }
+}
+
+/// Referral service for patients referred elsewhere and referrals received,
+/// their acceptance, the appointments booked for them and their letters
+pub struct ReferralService {
+    pool: PgPool,
+    events: EventBus,
+    patients: Arc<PatientService>,
+    conditions: Arc<ConditionService>,
+}
+
+impl ReferralService {
+    /// Create the service publishing to a shared event bus, reading patients
+    /// and their problems for letters
+    pub fn new(pool: PgPool, events: EventBus, patients: Arc<PatientService>, conditions: Arc<ConditionService>) -> Self {
+        Self {
+            pool,
+            events,
+            patients,
+            conditions,
+        }
+    }
+
+    /// Check the conditions a referral is for are the referral's patient's
+    async fn check_reasons(&self, referral: &Referral) -> Result<(), HimsError> {
+        for condition_id in &referral.reason_condition_ids {
+            match self.conditions.get(*condition_id).await? {
+                Some(condition) if condition.patient_id == referral.patient_id => {}
+                Some(condition) => {
+                    return Err(HimsError::ValidationError {
+                        message: format!("Condition {} is for patient {}", condition_id, condition.patient_id),
+                    })
+                }
+                None => {
+                    return Err(HimsError::ValidationError {
+                        message: format!("Condition {} does not exist", condition_id),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn insert(&self, referral: &Referral) -> Result<(), sqlx::Error> {
+        sqlx::query(INSERT_REFERRAL)
+            .bind(referral.id)
+            .bind(referral.patient_id)
+            .bind(referral.direction.as_str())
+            .bind(referral.status.as_str())
+            .bind(referral.priority.as_str())
+            .bind(&referral.identifier)
+            .bind(referral.code.as_ref().and_then(|code| serde_json::to_value(code).ok()))
+            .bind(referral.specialty.as_ref().and_then(|specialty| serde_json::to_value(specialty).ok()))
+            .bind(serde_json::to_value(&referral.reason_code).unwrap_or_default())
+            .bind(&referral.reason_condition_ids)
+            .bind(referral.requester_id)
+            .bind(&referral.requester_display)
+            .bind(referral.performer_id)
+            .bind(&referral.performer_display)
+            .bind(referral.acceptance.as_str())
+            .bind(referral.responded_at)
+            .bind(&referral.response_note)
+            .bind(referral.appointment_id)
+            .bind(referral.authored_on)
+            .bind(&referral.note)
+            .bind(referral.created_at)
+            .bind(referral.updated_at)
+            .bind(serde_json::to_value(&referral.meta).unwrap_or_default())
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Create an outbound referral
+    pub async fn create(&self, request: ReferralRequest) -> Result<Referral, HimsError> {
+        let referral: Referral = request.into();
+        check_referral(&referral)?;
+        self.check_reasons(&referral).await?;
+
+        self.insert(&referral)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.events.publish(DomainEvent::ReferralCreated {
+            referral_id: referral.id,
+            patient_id: referral.patient_id,
+        });
+        Ok(referral)
+    }
+
+    /// Get referral by ID
+    pub async fn get(&self, id: Uuid) -> Result<Option<Referral>, HimsError> {
+        let row = sqlx::query(GET_REFERRAL_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(row.as_ref().map(referral_from_row))
+    }
+
+    /// Search referrals, a page at a time
+    pub async fn search(&self, search: &ReferralSearch) -> Result<ReferralSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_REFERRALS);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &SEARCH_KEYS);
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut fetched = Vec::new();
+        for row in rows {
+            let referral = referral_from_row(&row);
+            fetched.push((referral, RowKey::from_column(row.get("page_key"))?));
+        }
+        let page = search.page.page(fetched);
+
+        let total = count_matches(&self.pool, search.page.total(), COUNT_REFERRALS, ESTIMATE_REFERRALS, |query| {
+            search.push_filters(query)
+        })
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(ReferralSearchResult {
+            referrals: page.items,
+            total,
+            next: page.next,
+            previous: page.previous,
+        })
+    }
+
+    /// Replace a referral if it is still at `expected_version`, or `None` if
+    /// there is no such referral. The status may only move along the FHIR
+    /// request workflow, and the referral stays for the same patient; its
+    /// direction, acceptance and appointment change only through receiving,
+    /// answering and scheduling it.
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: ReferralRequest,
+        expected_version: &str,
+    ) -> Result<Option<Referral>, HimsError> {
+        let Some(current) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let current_version = current.meta.version_id.as_deref().unwrap_or("1");
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Referral {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+        if request.patient_id != current.patient_id {
+            return Err(HimsError::ValidationError {
+                message: format!("Referral {} cannot move to another patient", id),
+            });
+        }
+        if !current.status.can_transition_to(request.status) {
+            return Err(HimsError::ValidationError {
+                message: format!(
+                    "Referral {} cannot go from {} to {}",
+                    id,
+                    current.status.as_str(),
+                    request.status.as_str()
+                ),
+            });
+        }
+
+        let mut referral: Referral = request.into();
+        referral.direction = current.direction;
+        check_referral(&referral)?;
+        self.check_reasons(&referral).await?;
+
+        let rows_affected = sqlx::query(UPDATE_REFERRAL)
+            .bind(id)
+            .bind(referral.status.as_str())
+            .bind(referral.priority.as_str())
+            .bind(referral.code.as_ref().and_then(|code| serde_json::to_value(code).ok()))
+            .bind(referral.specialty.as_ref().and_then(|specialty| serde_json::to_value(specialty).ok()))
+            .bind(serde_json::to_value(&referral.reason_code).unwrap_or_default())
+            .bind(&referral.reason_condition_ids)
+            .bind(referral.requester_id)
+            .bind(&referral.requester_display)
+            .bind(referral.performer_id)
+            .bind(&referral.performer_display)
+            .bind(&referral.note)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Referral {} was modified concurrently", id),
+            });
+        }
+        self.events.publish(DomainEvent::ReferralUpdated { referral_id: id });
+
+        self.get(id).await
+    }
+
+    /// Record the referred-to party's answer to an open referral, or `None`
+    /// if there is no such referral. A referral is answered once.
+    async fn respond(
+        &self,
+        id: Uuid,
+        acceptance: ReferralAcceptance,
+        note: Option<String>,
+    ) -> Result<Option<Referral>, HimsError> {
+        let Some(current) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let rows_affected = sqlx::query(RESPOND_TO_REFERRAL)
+            .bind(id)
+            .bind(acceptance.as_str())
+            .bind(note)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            let message = if current.acceptance != ReferralAcceptance::Pending {
+                format!("Referral {} was already {}", id, current.acceptance.as_str())
+            } else {
+                format!("Referral {} is {} and cannot be answered", id, current.status.as_str())
+            };
+            return Err(HimsError::ConflictError { message });
+        }
+        self.events.publish(match acceptance {
+            ReferralAcceptance::Declined => DomainEvent::ReferralDeclined { referral_id: id },
+            _ => DomainEvent::ReferralAccepted { referral_id: id },
+        });
+
+        self.get(id).await
+    }
+
+    /// Accept an open referral, with an optional note for the referrer
+    pub async fn accept(&self, id: Uuid, note: Option<String>) -> Result<Option<Referral>, HimsError> {
+        self.respond(id, ReferralAcceptance::Accepted, note).await
+    }
+
+    /// Decline an open referral, saying why; it is revoked
+    pub async fn decline(&self, id: Uuid, reason: String) -> Result<Option<Referral>, HimsError> {
+        if reason.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Declining a referral needs a reason".to_string(),
+            });
+        }
+        self.respond(id, ReferralAcceptance::Declined, Some(reason)).await
+    }
+
+    /// Link the appointment booked for an accepted open referral, or `None`
+    /// if there is no such referral. The appointment is for the referral's
+    /// patient.
+    pub async fn link_appointment(&self, id: Uuid, appointment_id: Uuid) -> Result<Option<Referral>, HimsError> {
+        let Some(current) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let patient_id: Option<Uuid> = sqlx::query_scalar(GET_APPOINTMENT_PATIENT)
+            .bind(appointment_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        match patient_id {
+            Some(patient_id) if patient_id == current.patient_id => {}
+            Some(patient_id) => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Appointment {} is for patient {}", appointment_id, patient_id),
+                })
+            }
+            None => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Appointment {} does not exist", appointment_id),
+                })
+            }
+        }
+
+        let rows_affected = sqlx::query(LINK_REFERRAL_APPOINTMENT)
+            .bind(id)
+            .bind(appointment_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!(
+                    "Referral {} is {} and {}; only accepted open referrals are scheduled",
+                    id,
+                    current.status.as_str(),
+                    current.acceptance.as_str()
+                ),
+            });
+        }
+        self.events.publish(DomainEvent::ReferralScheduled {
+            referral_id: id,
+            appointment_id,
+        });
+
+        self.get(id).await
+    }
+
+    /// Soft delete referral
+    pub async fn delete(&self, id: Uuid) -> Result<bool, HimsError> {
+        let rows_affected = sqlx::query(SOFT_DELETE_REFERRAL)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected > 0 {
+            self.events.publish(DomainEvent::ReferralDeleted { referral_id: id });
+        }
+        Ok(rows_affected > 0)
+    }
+
+    /// The patient an inbound referral names, by ID or by an identifier
+    /// only one patient has
+    async fn resolve_patient(&self, patient: &InboundPatient) -> Result<Uuid, HimsError> {
+        match patient {
+            InboundPatient::Id(id) => match self.patients.get_patient(*id).await {
+                Ok(Some(patient)) => Ok(patient.id),
+                Ok(None) => Err(HimsError::ValidationError {
+                    message: format!("Patient {} does not exist", id),
+                }),
+                Err(e) => Err(HimsError::DatabaseError(e.to_string())),
+            },
+            InboundPatient::Identifier(value) => {
+                let ids: Vec<Uuid> = sqlx::query_scalar(FIND_PATIENTS_BY_IDENTIFIER_VALUE)
+                    .bind(value)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+                match ids.as_slice() {
+                    [id] => Ok(*id),
+                    [] => Err(HimsError::ValidationError {
+                        message: format!("No patient has identifier {}", value),
+                    }),
+                    _ => Err(HimsError::ValidationError {
+                        message: format!("Several patients have identifier {}", value),
+                    }),
+                }
+            }
+        }
+    }
+
+    /// File a referral received from another organization against the
+    /// patient it names. A referral the sender numbered is filed once;
+    /// receiving it again returns the one filed, with `false`.
+    pub async fn receive(&self, inbound: InboundReferral) -> Result<(Referral, bool), HimsError> {
+        let mut referral = inbound.referral;
+        referral.patient_id = self.resolve_patient(&inbound.patient).await?;
+
+        if let Some(identifier) = &referral.identifier {
+            let row = sqlx::query(FIND_INBOUND_REFERRAL)
+                .bind(&referral.requester_display)
+                .bind(identifier)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            if let Some(row) = row {
+                return Ok((referral_from_row(&row), false));
+            }
+        }
+
+        self.insert(&referral).await.map_err(|e| match &e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError {
+                message: format!("Referral {} was received concurrently", referral.identifier.as_deref().unwrap_or_default()),
+            },
+            _ => HimsError::DatabaseError(e.to_string()),
+        })?;
+
+        self.events.publish(DomainEvent::ReferralReceived {
+            referral_id: referral.id,
+            patient_id: referral.patient_id,
+        });
+        Ok((referral, true))
+    }
+
+    /// A referral with its patient and the patient's current problems as
+    /// disclosed to the referral's recipient for treatment, or `None` if
+    /// there is no such referral
+    async fn letter_context(&self, id: Uuid) -> Result<Option<(Referral, Patient, SegmentedConditions)>, HimsError> {
+        let Some(referral) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let patient = self
+            .patients
+            .get_patient(referral.patient_id)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| HimsError::InternalError {
+                message: format!("Patient {} of referral {} does not exist", referral.patient_id, id),
+            })?;
+        let problems = self
+            .conditions
+            .problem_list_disclosure(
+                referral.patient_id,
+                false,
+                referral.recipient().as_deref(),
+                Some(PurposeOfUse::Treatment),
+            )
+            .await?;
+        Ok(Some((referral, patient, problems)))
+    }
+
+    /// The referral letter as a PDF, or `None` if there is no such referral.
+    /// Letters including Part 2 records end with the notice against
+    /// redisclosure.
+    pub async fn letter_pdf(&self, id: Uuid) -> Result<Option<Vec<u8>>, HimsError> {
+        Ok(self.letter_context(id).await?.map(|(referral, patient, problems)| {
+            let mut letter = referral_letter(&referral, &patient, &problems.conditions, Utc::now());
+            if problems.part2 {
+                letter.space();
+                letter.paragraph(PART2_REDISCLOSURE_NOTICE);
+            }
+            letter.render()
+        }))
+    }
+
+    /// The referral as a C-CDA Referral Note, or `None` if there is no such
+    /// referral
+    pub async fn letter_ccda(&self, id: Uuid) -> Result<Option<String>, HimsError> {
+        Ok(self
+            .letter_context(id)
+            .await?
+            .map(|(referral, patient, problems)| {
+                if problems.part2 {
+                    CcdaReferralNote::generate_part2(&referral, &patient, &problems.conditions, Utc::now())
+                } else {
+                    CcdaReferralNote::generate(&referral, &patient, &problems.conditions, Utc::now())
+                }
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_check_referral() {
+        let mut referral = Referral::new(Uuid::new_v4(), ReferralDirection::Outbound);
+        assert!(check_referral(&referral).is_err(), "nowhere to refer to");
+        referral.performer_display = Some("Dr Ngozi Okafor".to_string());
+        assert!(check_referral(&referral).is_err(), "no reason");
+        referral.reason_condition_ids.push(Uuid::new_v4());
+        assert!(check_referral(&referral).is_ok());
+
+        let inbound = Referral::new(Uuid::new_v4(), ReferralDirection::Inbound);
+        assert!(check_referral(&inbound).is_ok(), "inbound referrals are taken as sent");
+
+        assert!(ReferralStatus::Draft.can_transition_to(ReferralStatus::Active));
+        assert!(ReferralStatus::Active.can_transition_to(ReferralStatus::Completed));
+        assert!(ReferralStatus::Completed.can_transition_to(ReferralStatus::EnteredInError));
+        assert!(!ReferralStatus::Revoked.can_transition_to(ReferralStatus::Active));
+    }
+
+    #[test]
+    fn test_search_from_params() {
+        let patient = Uuid::new_v4();
+        let search = ReferralSearch::from_params(&params(&[
+            ("patient", format!("Patient/{}", patient).as_str()),
+            ("direction", "inbound"),
+            ("acceptance", "pending"),
+        ]))
+        .unwrap();
+        assert_eq!(search.patient, vec![patient]);
+        assert_eq!(search.direction, Some(ReferralDirection::Inbound));
+
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM referrals WHERE deleted_at IS NULL");
+        search.push_filters(&mut query);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM referrals WHERE deleted_at IS NULL AND patient_id = ANY($1) AND direction = $2 AND acceptance = ANY($3)"
+        );
+
+        assert!(ReferralSearch::from_params(&params(&[("direction", "sideways")])).is_err());
+        assert!(ReferralSearch::from_params(&params(&[("acceptance", "maybe")])).is_err());
+        assert!(ReferralSearch::from_params(&params(&[("status", "requested")])).is_err());
+    }
+
+    #[test]
+    fn test_advertised_params_are_supported() {
+        let id = Uuid::new_v4();
+        for param in ReferralSearch::SEARCH_PARAMS {
+            let value = match param.name {
+                "status" => "active".to_string(),
+                "priority" => "urgent".to_string(),
+                "direction" => "outbound".to_string(),
+                "acceptance" => "declined".to_string(),
+                _ => id.to_string(),
+            };
+            let search = ReferralSearch::from_params(&params(&[(param.name, value.as_str())])).unwrap();
+            assert_ne!(search, ReferralSearch::default(), "{} was ignored", param.name);
+        }
+    }
+}
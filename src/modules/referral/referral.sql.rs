@@ -0,0 +1,118 @@
+//! Referral SQL Queries
+//! 
+//! This file contains all SQL queries used by the referral service.
+
+/// Insert a new referral
+pub const INSERT_REFERRAL: &str = r#"
+    INSERT INTO referrals (
+        id, patient_id, direction, status, priority, identifier, code, specialty, reason_code,
+        reason_condition_ids, requester_id, requester_display, performer_id, performer_display,
+        acceptance, responded_at, response_note, appointment_id, authored_on, note,
+        created_at, updated_at, meta
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+        $21, $22, $23
+    )
+"#;
+
+/// Get referral by ID
+pub const GET_REFERRAL_BY_ID: &str = r#"
+    SELECT id, patient_id, direction, status, priority, identifier, code, specialty, reason_code,
+           reason_condition_ids, requester_id, requester_display, performer_id, performer_display,
+           acceptance, responded_at, response_note, appointment_id, authored_on, note,
+           created_at, updated_at, meta
+    FROM referrals
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Inbound referral with the sender's number $2 from sender $1
+pub const FIND_INBOUND_REFERRAL: &str = r#"
+    SELECT id, patient_id, direction, status, priority, identifier, code, specialty, reason_code,
+           reason_condition_ids, requester_id, requester_display, performer_id, performer_display,
+           acceptance, responded_at, response_note, appointment_id, authored_on, note,
+           created_at, updated_at, meta
+    FROM referrals
+    WHERE direction = 'inbound' AND requester_display IS NOT DISTINCT FROM $1 AND identifier = $2
+      AND deleted_at IS NULL
+"#;
+
+/// Replace a referral if it is still at version $14, moving it to version
+/// $13. The patient, direction, acceptance and appointment are left alone.
+pub const UPDATE_REFERRAL: &str = r#"
+    UPDATE referrals
+    SET status = $2, priority = $3, code = $4, specialty = $5, reason_code = $6,
+        reason_condition_ids = $7, requester_id = $8, requester_display = $9, performer_id = $10,
+        performer_display = $11, note = $12, updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($13::text))
+    WHERE id = $1 AND deleted_at IS NULL AND COALESCE(meta->>'version_id', '1') = $14
+"#;
+
+/// Record the answer $2 to a pending open referral with note $3, moving it
+/// to the next version. A declined referral is revoked.
+pub const RESPOND_TO_REFERRAL: &str = r#"
+    UPDATE referrals
+    SET acceptance = $2, response_note = $3, responded_at = NOW(),
+        status = CASE WHEN $2 = 'declined' THEN 'revoked' ELSE status END,
+        updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL AND acceptance = 'pending' AND status IN ('active', 'on-hold')
+"#;
+
+/// Link appointment $2 to an accepted open referral, moving it to the next
+/// version
+pub const LINK_REFERRAL_APPOINTMENT: &str = r#"
+    UPDATE referrals
+    SET appointment_id = $2, updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL AND acceptance = 'accepted' AND status IN ('active', 'on-hold')
+"#;
+
+/// Soft delete referral
+pub const SOFT_DELETE_REFERRAL: &str = r#"
+    UPDATE referrals
+    SET deleted_at = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Start of a referral search, most recently authored first; the service
+/// appends the filters and the page's cursor, order and limit
+pub const SEARCH_REFERRALS: &str = r#"
+    SELECT id, patient_id, direction, status, priority, identifier, code, specialty, reason_code,
+           reason_condition_ids, requester_id, requester_display, performer_id, performer_display,
+           acceptance, responded_at, response_note, appointment_id, authored_on, note,
+           created_at, updated_at, meta,
+           ARRAY[authored_on::text, id::text] AS page_key
+    FROM referrals
+    WHERE deleted_at IS NULL
+"#;
+
+/// Start of an accurate referral search total
+pub const COUNT_REFERRALS: &str = r#"
+    SELECT COUNT(*) FROM referrals WHERE deleted_at IS NULL
+"#;
+
+/// Query plan of a referral search, whose row estimate answers
+/// `_total=estimate`
+pub const ESTIMATE_REFERRALS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM referrals WHERE deleted_at IS NULL
+"#;
+
+/// Patient of an appointment, to check an appointment linked to a referral
+/// is for the referral's patient
+pub const GET_APPOINTMENT_PATIENT: &str = r#"
+    SELECT patient_id FROM appointments WHERE id = $1
+"#;
+
+/// Active patients with an identifier of this value, whatever its system.
+/// At most two, to tell one match from several.
+pub const FIND_PATIENTS_BY_IDENTIFIER_VALUE: &str = r#"
+    SELECT id
+    FROM patients
+    WHERE active = true AND identifier @> jsonb_build_array(jsonb_build_object('value', $1::text))
+    ORDER BY id
+    LIMIT 2
+"#;
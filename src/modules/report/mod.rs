@@ -0,0 +1,56 @@
+//! Report Module
+//!
+//! This module lets users build and share reports including:
+//! - De-identified reporting views with pseudonymous keys and month-level dates
+//! - Saved report definitions as a query tree or checked, read-only SQL
+//! - Typed report parameters and per-report role permissions
+//! - Scheduled runs with CSV, PDF and XLSX export
+
+#[path = "report.controller.rs"]
+pub mod report_controller;
+#[path = "report.service.rs"]
+pub mod report_service;
+#[path = "report.query.rs"]
+pub mod report_query;
+#[path = "report.statement.rs"]
+pub mod report_statement;
+#[path = "report.sql.rs"]
+pub mod report_sql;
+
+pub use report_controller::ReportController;
+pub use report_query::{ReportQuery, ReportResult, ReportView};
+pub use report_service::{ReportDefinition, ReportFormat, ReportService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Report Module Configuration
+pub struct ReportModule {
+    pub service: Arc<ReportService>,
+    pub controller: Arc<ReportController>,
+}
+
+impl ReportModule {
+    /// Create a new Report Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(ReportService::new(db_pool));
+        let controller = Arc::new(ReportController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<ReportService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,244 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::report::report_query::ReportView;
+use crate::modules::report::report_service::{
+    ReportDefinition, ReportDefinitionRequest, ReportFile, ReportRun, RunOutput, RunRequest,
+};
+use crate::modules::report::ReportService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Report controller for saved reports over the de-identified reporting
+/// views
+pub struct ReportController {
+    report_service: Arc<ReportService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl ReportController {
+    /// Create new controller with injected service
+    pub fn new(report_service: Arc<ReportService>) -> Self {
+        Self { report_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/views", Self::list_views, "List the reporting views and their columns")
+            .post("/", Self::create_definition, "Save a report")
+            .get("/", Self::list_definitions, "List saved reports")
+            .get("/:id", Self::get_definition, "Get a saved report")
+            .put("/:id", Self::update_definition, "Replace a saved report")
+            .delete("/:id", Self::delete_definition, "Retire a saved report")
+            .post("/:id/run", Self::run, "Run a report as JSON, CSV, PDF or XLSX")
+            .get("/:id/runs", Self::list_runs, "List a report's latest runs")
+            .get("/:id/runs/:run_id/output", Self::run_output, "Download the output of a scheduled run")
+            .with_state(self.report_service.clone())
+    }
+
+    /// List the reporting views and their columns
+    pub async fn list_views(State(service): State<Arc<ReportService>>) -> Json<&'static [ReportView]> {
+        Json(service.views())
+    }
+
+    /// Save a report
+    pub async fn create_definition(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+        Json(payload): Json<ReportDefinitionRequest>,
+    ) -> Result<(StatusCode, Json<ReportDefinition>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.create_definition(payload, actor).await {
+            Ok(definition) => Ok((StatusCode::CREATED, Json(definition))),
+            Err(e) => Err(Self::error_response("Failed to save report", e)),
+        }
+    }
+
+    /// List saved reports
+    pub async fn list_definitions(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<ReportDefinition>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_definitions(actor).await {
+            Ok(definitions) => Ok(Json(definitions)),
+            Err(e) => Err(Self::error_response("Failed to list reports", e)),
+        }
+    }
+
+    /// Get a saved report
+    pub async fn get_definition(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<ReportDefinition>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.find_definition(id, actor).await {
+            Ok(Some(definition)) => Ok(Json(definition)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve report", e)),
+        }
+    }
+
+    /// Replace a saved report
+    pub async fn update_definition(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<ReportDefinitionRequest>,
+    ) -> Result<Json<ReportDefinition>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.update_definition(id, payload, actor).await {
+            Ok(Some(definition)) => Ok(Json(definition)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update report", e)),
+        }
+    }
+
+    /// Retire a saved report
+    pub async fn delete_definition(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.delete_definition(id, actor).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retire report", e)),
+        }
+    }
+
+    /// Run a report. JSON comes back as columns and rows; other formats as a
+    /// download.
+    pub async fn run(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RunRequest>,
+    ) -> Result<Response, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.run(id, payload, actor).await {
+            Ok(Some(RunOutput::Rows(result))) => Ok(Json(result).into_response()),
+            Ok(Some(RunOutput::File(file))) => Ok(Self::download(file)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to run report", e)),
+        }
+    }
+
+    /// List a report's latest runs
+    pub async fn list_runs(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<ReportRun>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_runs(id, actor).await {
+            Ok(Some(runs)) => Ok(Json(runs)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to list report runs", e)),
+        }
+    }
+
+    /// Download the output of a scheduled run
+    pub async fn run_output(
+        State(service): State<Arc<ReportService>>,
+        headers: HeaderMap,
+        Path((id, run_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<Response, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.run_output(id, run_id, actor).await {
+            Ok(Some(file)) => Ok(Self::download(file)),
+            Ok(None) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Report output not found".to_string(),
+                    message: format!("Run {} of report {} has no kept output", run_id, id),
+                }),
+            )),
+            Err(e) => Err(Self::error_response("Failed to retrieve report output", e)),
+        }
+    }
+
+    /// A report file as a download, never rendered in the API's origin
+    fn download(file: ReportFile) -> Response {
+        let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", file.file_name))
+            .unwrap_or(HeaderValue::from_static("attachment"));
+        (
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static(file.content_type)),
+                (header::CONTENT_DISPOSITION, disposition),
+                (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                (header::CACHE_CONTROL, HeaderValue::from_static("private, no-store")),
+            ],
+            file.bytes,
+        )
+            .into_response()
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(id: Uuid) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Report not found".to_string(),
+                message: format!("Report with id {} not found", id),
+            }),
+        )
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,769 @@
+use chrono::NaiveDate;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::exporters::tabular::{Cell, Table};
+
+/// Most rows a run of a report returns
+pub const MAX_ROWS: usize = 10_000;
+
+/// Most values an `in` filter may list
+const MAX_LIST_VALUES: usize = 1_000;
+
+/// Type of a view column or report parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueType {
+    Text,
+    Integer,
+    Number,
+    /// A date, as `YYYY-MM-DD`
+    Date,
+    Boolean,
+}
+
+impl ValueType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueType::Text => "text",
+            ValueType::Integer => "integer",
+            ValueType::Number => "number",
+            ValueType::Date => "date",
+            ValueType::Boolean => "boolean",
+        }
+    }
+
+    /// A JSON value as this type. Strings are parsed, as parameters given
+    /// in a query string arrive as text.
+    pub fn bind(&self, value: &Value) -> Result<BindValue, String> {
+        let wrong = || format!("{} is not a {} value", value, self.as_str());
+        match (self, value) {
+            (_, Value::Null) => Ok(BindValue::Null(*self)),
+            (ValueType::Text, Value::String(text)) => Ok(BindValue::Text(text.clone())),
+            (ValueType::Integer, Value::Number(number)) => number.as_i64().map(BindValue::Integer).ok_or_else(wrong),
+            (ValueType::Integer, Value::String(text)) => {
+                text.trim().parse().map(BindValue::Integer).map_err(|_| wrong())
+            }
+            (ValueType::Number, Value::Number(number)) => number.as_f64().map(BindValue::Number).ok_or_else(wrong),
+            (ValueType::Number, Value::String(text)) => text.trim().parse().map(BindValue::Number).map_err(|_| wrong()),
+            (ValueType::Date, Value::String(text)) => NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+                .map(BindValue::Date)
+                .map_err(|_| wrong()),
+            (ValueType::Boolean, Value::Bool(flag)) => Ok(BindValue::Boolean(*flag)),
+            (ValueType::Boolean, Value::String(text)) => match text.trim() {
+                "true" => Ok(BindValue::Boolean(true)),
+                "false" => Ok(BindValue::Boolean(false)),
+                _ => Err(wrong()),
+            },
+            _ => Err(wrong()),
+        }
+    }
+}
+
+/// A value bound to a query placeholder
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindValue {
+    /// SQL NULL of a type
+    Null(ValueType),
+    Text(String),
+    Integer(i64),
+    Number(f64),
+    Date(NaiveDate),
+    Boolean(bool),
+    TextList(Vec<String>),
+    IntegerList(Vec<i64>),
+    NumberList(Vec<f64>),
+    DateList(Vec<NaiveDate>),
+}
+
+impl BindValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, BindValue::Null(_))
+    }
+
+    /// A non-empty list of values of a type, for an `in` filter
+    fn list(value_type: ValueType, values: &[Value]) -> Result<Self, String> {
+        if value_type == ValueType::Boolean {
+            return Err("An in filter cannot be on a boolean column".to_string());
+        }
+        if values.is_empty() || values.len() > MAX_LIST_VALUES {
+            return Err(format!("An in filter lists 1 to {} values", MAX_LIST_VALUES));
+        }
+        let mut list = match value_type {
+            ValueType::Integer => BindValue::IntegerList(Vec::new()),
+            ValueType::Number => BindValue::NumberList(Vec::new()),
+            ValueType::Date => BindValue::DateList(Vec::new()),
+            _ => BindValue::TextList(Vec::new()),
+        };
+        for value in values {
+            match (&mut list, value_type.bind(value)?) {
+                (BindValue::TextList(list), BindValue::Text(value)) => list.push(value),
+                (BindValue::IntegerList(list), BindValue::Integer(value)) => list.push(value),
+                (BindValue::NumberList(list), BindValue::Number(value)) => list.push(value),
+                (BindValue::DateList(list), BindValue::Date(value)) => list.push(value),
+                _ => return Err("An in filter cannot list null".to_string()),
+            }
+        }
+        Ok(list)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ViewColumn {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+}
+
+/// A de-identified view in the `reporting` schema, which reports query
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ReportView {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub columns: &'static [ViewColumn],
+}
+
+impl ReportView {
+    pub fn column(&self, name: &str) -> Option<&ViewColumn> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+const fn column(name: &'static str, value_type: ValueType) -> ViewColumn {
+    ViewColumn { name, value_type }
+}
+
+/// The views of the `reporting` schema, as its migration defines them
+pub const REPORT_VIEWS: &[ReportView] = &[
+    ReportView {
+        name: "patients",
+        description: "Patients, by pseudonymous key, with birth year and five-year age band",
+        columns: &[
+            column("patient_key", ValueType::Text),
+            column("gender", ValueType::Text),
+            column("birth_year", ValueType::Integer),
+            column("age_band", ValueType::Integer),
+            column("deceased", ValueType::Boolean),
+            column("active", ValueType::Boolean),
+            column("state", ValueType::Text),
+            column("country", ValueType::Text),
+        ],
+    },
+    ReportView {
+        name: "encounters",
+        description: "Encounters, with the months they started and ended in and their length in days",
+        columns: &[
+            column("encounter_key", ValueType::Text),
+            column("patient_key", ValueType::Text),
+            column("status", ValueType::Text),
+            column("class", ValueType::Text),
+            column("service_type", ValueType::Text),
+            column("organization_id", ValueType::Text),
+            column("start_month", ValueType::Date),
+            column("end_month", ValueType::Date),
+            column("length_of_stay", ValueType::Integer),
+            column("discharge_disposition", ValueType::Text),
+        ],
+    },
+    ReportView {
+        name: "appointments",
+        description: "Appointments, with the month they were for",
+        columns: &[
+            column("appointment_key", ValueType::Text),
+            column("patient_key", ValueType::Text),
+            column("practitioner_id", ValueType::Text),
+            column("status", ValueType::Text),
+            column("service_type", ValueType::Text),
+            column("start_month", ValueType::Date),
+            column("minutes_duration", ValueType::Integer),
+        ],
+    },
+    ReportView {
+        name: "conditions",
+        description: "Recorded conditions by SNOMED CT concept, with onset year",
+        columns: &[
+            column("patient_key", ValueType::Text),
+            column("encounter_key", ValueType::Text),
+            column("snomed_code", ValueType::Text),
+            column("category", ValueType::Text),
+            column("clinical_status", ValueType::Text),
+            column("verification_status", ValueType::Text),
+            column("onset_year", ValueType::Integer),
+            column("recorded_month", ValueType::Date),
+        ],
+    },
+    ReportView {
+        name: "charges",
+        description: "Captured charges, amounts in the smallest unit of the currency",
+        columns: &[
+            column("charge_key", ValueType::Text),
+            column("patient_key", ValueType::Text),
+            column("encounter_key", ValueType::Text),
+            column("code_system", ValueType::Text),
+            column("code", ValueType::Text),
+            column("modifiers", ValueType::Text),
+            column("units", ValueType::Integer),
+            column("unit_price", ValueType::Integer),
+            column("amount", ValueType::Integer),
+            column("service_month", ValueType::Date),
+            column("status", ValueType::Text),
+        ],
+    },
+];
+
+/// A reporting view by name
+pub fn report_view(name: &str) -> Option<&'static ReportView> {
+    REPORT_VIEWS.iter().find(|view| view.name == name)
+}
+
+/// Whether a name can be a parameter or output column: lower case letters,
+/// digits and underscores, not starting with a digit
+pub fn is_identifier(name: &str) -> bool {
+    (1..=63).contains(&name.len())
+        && name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// A parameter a report declares, given a value each time it runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+    #[serde(default)]
+    pub required: bool,
+    /// Value used when a run gives none
+    #[serde(default)]
+    pub default: Option<Value>,
+}
+
+/// Check a report's declared parameters
+pub fn check_parameters(parameters: &[ReportParameter]) -> Result<(), String> {
+    for (index, parameter) in parameters.iter().enumerate() {
+        if !is_identifier(&parameter.name) {
+            return Err(format!("{:?} is not a parameter name", parameter.name));
+        }
+        if parameters[..index].iter().any(|other| other.name == parameter.name) {
+            return Err(format!("Parameter {} is declared twice", parameter.name));
+        }
+        if let Some(default) = &parameter.default {
+            parameter
+                .value_type
+                .bind(default)
+                .map_err(|e| format!("Default of parameter {}: {}", parameter.name, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Values of a report's parameters for a run, from those given and the
+/// defaults. Parameters given no value are null; required ones must have
+/// one.
+pub fn resolve_parameters(
+    declared: &[ReportParameter],
+    given: &serde_json::Map<String, Value>,
+) -> Result<HashMap<String, BindValue>, String> {
+    if let Some(unknown) = given.keys().find(|name| !declared.iter().any(|parameter| &parameter.name == *name)) {
+        return Err(format!("The report has no parameter {}", unknown));
+    }
+    let mut values = HashMap::new();
+    for parameter in declared {
+        let value = given.get(&parameter.name).or(parameter.default.as_ref()).unwrap_or(&Value::Null);
+        let bound = parameter
+            .value_type
+            .bind(value)
+            .map_err(|e| format!("Parameter {}: {}", parameter.name, e))?;
+        if parameter.required && bound.is_null() {
+            return Err(format!("Parameter {} is required", parameter.name));
+        }
+        values.insert(parameter.name.clone(), bound);
+    }
+    Ok(values)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::CountDistinct => "count_distinct",
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+/// A column of a report's output: a view column, or an aggregate of one.
+/// `count` needs no column, counting rows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectItem {
+    pub column: Option<String>,
+    pub aggregate: Option<Aggregate>,
+    /// Name of the output column; by default the view column's, or the
+    /// aggregate's and the column's, e.g. `sum_amount`
+    pub alias: Option<String>,
+}
+
+impl SelectItem {
+    fn output_name(&self) -> String {
+        match (&self.alias, &self.aggregate, &self.column) {
+            (Some(alias), _, _) => alias.clone(),
+            (None, Some(aggregate), Some(column)) => format!("{}_{}", aggregate.as_str(), column),
+            (None, Some(aggregate), None) => aggregate.as_str().to_string(),
+            (None, None, column) => column.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// One of a list of literal values
+    In,
+    /// SQL `LIKE` pattern, for text columns
+    Like,
+    IsNull,
+    NotNull,
+}
+
+impl FilterOp {
+    fn operator(&self) -> &'static str {
+        match self {
+            FilterOp::Eq => "=",
+            FilterOp::Ne => "<>",
+            FilterOp::Lt => "<",
+            FilterOp::Le => "<=",
+            FilterOp::Gt => ">",
+            FilterOp::Ge => ">=",
+            FilterOp::In => "= ANY",
+            FilterOp::Like => "LIKE",
+            FilterOp::IsNull => "IS NULL",
+            FilterOp::NotNull => "IS NOT NULL",
+        }
+    }
+}
+
+/// What a column is compared with: a report parameter, as
+/// `{"param": "from"}`, or a literal value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Operand {
+    Parameter { param: String },
+    Literal(Value),
+}
+
+/// A condition rows must meet. A filter on a parameter left null is not
+/// applied, so optional parameters narrow a report only when given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Filter {
+    pub column: String,
+    pub op: FilterOp,
+    #[serde(default)]
+    pub value: Option<Operand>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderItem {
+    /// An output column, or a column of the view if the report does not
+    /// group
+    pub column: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// A report as a query tree over one reporting view, compiled to SQL
+/// against the view's known columns, so nothing but the view can be read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportQuery {
+    pub source: String,
+    pub select: Vec<SelectItem>,
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    #[serde(default)]
+    pub order_by: Vec<OrderItem>,
+    pub limit: Option<usize>,
+}
+
+/// SQL of a report, the values of its placeholders in order, and the names
+/// of its output columns where they are known before it runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledQuery {
+    pub sql: String,
+    pub binds: Vec<BindValue>,
+    pub columns: Vec<String>,
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+impl ReportQuery {
+    /// Check the query against its view and declared parameters, without
+    /// values for them
+    pub fn check(&self, parameters: &[ReportParameter]) -> Result<(), String> {
+        let values = parameters
+            .iter()
+            .map(|parameter| (parameter.name.clone(), BindValue::Null(parameter.value_type)))
+            .collect();
+        self.compile(&values).map(|_| ())
+    }
+
+    /// SQL selecting the query's output, with parameters' values bound
+    pub fn compile(&self, parameters: &HashMap<String, BindValue>) -> Result<CompiledQuery, String> {
+        let view = report_view(&self.source).ok_or_else(|| format!("{} is not a reporting view", self.source))?;
+        let view_column = |name: &str| {
+            view.column(name)
+                .ok_or_else(|| format!("View {} has no column {}", view.name, name))
+        };
+        if self.select.is_empty() || self.select.len() > 50 {
+            return Err("A report selects 1 to 50 columns".to_string());
+        }
+        if self.limit.is_some_and(|limit| limit == 0 || limit > MAX_ROWS) {
+            return Err(format!("A report's limit is 1 to {} rows", MAX_ROWS));
+        }
+        for name in &self.group_by {
+            view_column(name)?;
+        }
+        let grouped = !self.group_by.is_empty() || self.select.iter().any(|item| item.aggregate.is_some());
+
+        let mut binds = Vec::new();
+        let mut columns = Vec::new();
+        let mut selected = Vec::new();
+        for item in &self.select {
+            let name = item.output_name();
+            if !is_identifier(&name) {
+                return Err(format!("{:?} is not an output column name", name));
+            }
+            if columns.contains(&name) {
+                return Err(format!("The report has two columns named {}", name));
+            }
+            let expression = match (item.aggregate, &item.column) {
+                (None, None) => return Err("A selected column needs a column or an aggregate".to_string()),
+                (None, Some(column)) => {
+                    view_column(column)?;
+                    if grouped && !self.group_by.contains(column) {
+                        return Err(format!("{} is selected but neither grouped by nor aggregated", column));
+                    }
+                    quote(column)
+                }
+                (Some(Aggregate::Count), None) => "count(*)".to_string(),
+                (Some(aggregate), None) => return Err(format!("{} needs a column", aggregate.as_str())),
+                (Some(aggregate), Some(column)) => {
+                    let value_type = view_column(column)?.value_type;
+                    match aggregate {
+                        Aggregate::Count => format!("count({})", quote(column)),
+                        Aggregate::CountDistinct => format!("count(DISTINCT {})", quote(column)),
+                        Aggregate::Sum | Aggregate::Avg
+                            if !matches!(value_type, ValueType::Integer | ValueType::Number) =>
+                        {
+                            return Err(format!("{} of {} needs a numeric column", aggregate.as_str(), column));
+                        }
+                        Aggregate::Min | Aggregate::Max if value_type == ValueType::Boolean => {
+                            return Err(format!("{} of {} needs an ordered column", aggregate.as_str(), column));
+                        }
+                        aggregate => format!("{}({})", aggregate.as_str(), quote(column)),
+                    }
+                }
+            };
+            selected.push(format!("{} AS {}", expression, quote(&name)));
+            columns.push(name);
+        }
+
+        let mut conditions = Vec::new();
+        for filter in &self.filters {
+            let value_type = view_column(&filter.column)?.value_type;
+            let column = quote(&filter.column);
+            match (filter.op, &filter.value) {
+                (FilterOp::IsNull | FilterOp::NotNull, None) => {
+                    conditions.push(format!("{} {}", column, filter.op.operator()));
+                }
+                (FilterOp::IsNull | FilterOp::NotNull, Some(_)) => {
+                    return Err(format!("A null test of {} takes no value", filter.column));
+                }
+                (_, None) => return Err(format!("The filter on {} needs a value", filter.column)),
+                (FilterOp::In, Some(Operand::Literal(Value::Array(values)))) => {
+                    binds.push(BindValue::list(value_type, values)?);
+                    conditions.push(format!("{} = ANY(${})", column, binds.len()));
+                }
+                (FilterOp::In, Some(_)) => {
+                    return Err(format!("The in filter on {} needs a list of values", filter.column));
+                }
+                (op, Some(operand)) => {
+                    if op == FilterOp::Like && value_type != ValueType::Text {
+                        return Err(format!("like needs a text column, not {}", filter.column));
+                    }
+                    if value_type == ValueType::Boolean && !matches!(op, FilterOp::Eq | FilterOp::Ne) {
+                        return Err(format!("{} can only be compared for equality", filter.column));
+                    }
+                    match operand {
+                        Operand::Parameter { param } => {
+                            let value = parameters
+                                .get(param)
+                                .ok_or_else(|| format!("The report has no parameter {}", param))?;
+                            let param_type = match value {
+                                BindValue::Null(value_type) => Some(*value_type),
+                                BindValue::Text(_) => Some(ValueType::Text),
+                                BindValue::Integer(_) => Some(ValueType::Integer),
+                                BindValue::Number(_) => Some(ValueType::Number),
+                                BindValue::Date(_) => Some(ValueType::Date),
+                                BindValue::Boolean(_) => Some(ValueType::Boolean),
+                                _ => None,
+                            };
+                            if param_type != Some(value_type) {
+                                return Err(format!(
+                                    "Parameter {} is not of the type of {}, {}",
+                                    param,
+                                    filter.column,
+                                    value_type.as_str()
+                                ));
+                            }
+                            binds.push(value.clone());
+                            conditions.push(format!(
+                                "(${n} IS NULL OR {} {} ${n})",
+                                column,
+                                op.operator(),
+                                n = binds.len()
+                            ));
+                        }
+                        Operand::Literal(value) => match value_type.bind(value)? {
+                            BindValue::Null(_) => {
+                                return Err(format!("Compare {} with null using is_null", filter.column));
+                            }
+                            bound => {
+                                binds.push(bound);
+                                conditions.push(format!("{} {} ${}", column, op.operator(), binds.len()));
+                            }
+                        },
+                    }
+                }
+            }
+        }
+
+        let mut ordering = Vec::new();
+        for item in &self.order_by {
+            let known = columns.contains(&item.column)
+                || if grouped { self.group_by.contains(&item.column) } else { view.column(&item.column).is_some() };
+            if !known {
+                return Err(format!("The report cannot be ordered by {}", item.column));
+            }
+            ordering.push(format!("{}{}", quote(&item.column), if item.descending { " DESC" } else { "" }));
+        }
+
+        let mut sql = format!("SELECT {} FROM reporting.{}", selected.join(", "), quote(view.name));
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        if !self.group_by.is_empty() {
+            let groups: Vec<String> = self.group_by.iter().map(|column| quote(column)).collect();
+            sql.push_str(&format!(" GROUP BY {}", groups.join(", ")));
+        }
+        if !ordering.is_empty() {
+            sql.push_str(&format!(" ORDER BY {}", ordering.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        Ok(CompiledQuery { sql, binds, columns })
+    }
+}
+
+/// Output of a run of a report
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    /// Whether rows past `MAX_ROWS` were left out
+    pub truncated: bool,
+}
+
+impl ReportResult {
+    /// Rows as Postgres gives them, each a JSON object of its columns in
+    /// order. The columns are those of the first row, or `columns` if there
+    /// are no rows.
+    pub fn from_json_rows(rows: &[String], columns: Vec<String>) -> Result<Self, String> {
+        let mut result = ReportResult {
+            columns,
+            truncated: rows.len() > MAX_ROWS,
+            ..Default::default()
+        };
+        for (index, row) in rows.iter().take(MAX_ROWS).enumerate() {
+            let entries = serde_json::from_str::<Entries>(row).map_err(|e| e.to_string())?.0;
+            if index == 0 {
+                result.columns = entries.iter().map(|(name, _)| name.clone()).collect();
+            }
+            result.rows.push(entries.into_iter().map(|(_, value)| value).collect());
+        }
+        Ok(result)
+    }
+
+    /// The output as a table to export
+    pub fn to_table(&self) -> Table {
+        let cell = |value: &Value| match value {
+            Value::Null => Cell::Empty,
+            Value::String(text) => Cell::Text(text.clone()),
+            Value::Number(number) => number.as_f64().map_or(Cell::Text(number.to_string()), Cell::Number),
+            other => Cell::Text(other.to_string()),
+        };
+        Table {
+            columns: self.columns.clone(),
+            rows: self.rows.iter().map(|row| row.iter().map(cell).collect()).collect(),
+        }
+    }
+}
+
+/// A JSON object's entries in the order they are written
+struct Entries(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for Entries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = Entries;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Entries, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Entries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn admissions() -> ReportQuery {
+        serde_json::from_value(json!({
+            "source": "encounters",
+            "select": [
+                { "column": "start_month" },
+                { "aggregate": "count", "alias": "admissions" },
+                { "aggregate": "avg", "column": "length_of_stay" },
+            ],
+            "filters": [
+                { "column": "class", "op": "in", "value": ["IMP", "ACUTE"] },
+                { "column": "start_month", "op": "ge", "value": { "param": "from" } },
+                { "column": "end_month", "op": "is_null" },
+            ],
+            "group_by": ["start_month"],
+            "order_by": [{ "column": "admissions", "descending": true }],
+            "limit": 100,
+        }))
+        .unwrap()
+    }
+
+    fn from_parameter() -> Vec<ReportParameter> {
+        serde_json::from_value(json!([{ "name": "from", "type": "date" }])).unwrap()
+    }
+
+    #[test]
+    fn test_compile() {
+        let declared = from_parameter();
+        assert!(admissions().check(&declared).is_ok());
+
+        let given = json!({ "from": "2024-01-01" });
+        let values = resolve_parameters(&declared, given.as_object().unwrap()).unwrap();
+        let compiled = admissions().compile(&values).unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT \"start_month\" AS \"start_month\", count(*) AS \"admissions\", \
+             avg(\"length_of_stay\") AS \"avg_length_of_stay\" FROM reporting.\"encounters\" \
+             WHERE \"class\" = ANY($1) AND ($2 IS NULL OR \"start_month\" >= $2) AND \"end_month\" IS NULL \
+             GROUP BY \"start_month\" ORDER BY \"admissions\" DESC LIMIT 100"
+        );
+        assert_eq!(
+            compiled.binds,
+            vec![
+                BindValue::TextList(vec!["IMP".to_string(), "ACUTE".to_string()]),
+                BindValue::Date(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+            ]
+        );
+        assert_eq!(compiled.columns, vec!["start_month", "admissions", "avg_length_of_stay"]);
+
+        let rejected = |change: fn(&mut ReportQuery)| {
+            let mut query = admissions();
+            change(&mut query);
+            query.check(&declared).is_err()
+        };
+        assert!(rejected(|query| query.source = "users".to_string()));
+        assert!(rejected(|query| query.select[0].column = Some("patient_name".to_string())));
+        assert!(rejected(|query| query.group_by.clear()));
+        assert!(rejected(|query| query.select[1].alias = Some("x\" FROM patients --".to_string())));
+        assert!(rejected(|query| query.select[2].column = Some("class".to_string())));
+        assert!(rejected(|query| query.filters[1].value = Some(Operand::Parameter { param: "to".to_string() })));
+        assert!(rejected(|query| query.filters[0].column = "length_of_stay".to_string()));
+        assert!(rejected(|query| query.order_by[0].column = "status".to_string()));
+        assert!(rejected(|query| query.limit = Some(MAX_ROWS + 1)));
+    }
+
+    #[test]
+    fn test_parameters() {
+        let declared: Vec<ReportParameter> = serde_json::from_value(json!([
+            { "name": "from", "type": "date", "required": true },
+            { "name": "ward", "type": "text", "default": "ICU" },
+            { "name": "min_days", "type": "integer" },
+        ]))
+        .unwrap();
+        assert!(check_parameters(&declared).is_ok());
+
+        let given = json!({ "from": "2024-05-01", "min_days": "3" });
+        let values = resolve_parameters(&declared, given.as_object().unwrap()).unwrap();
+        assert_eq!(values["ward"], BindValue::Text("ICU".to_string()));
+        assert_eq!(values["min_days"], BindValue::Integer(3));
+
+        let missing = json!({ "ward": "ER" });
+        assert!(resolve_parameters(&declared, missing.as_object().unwrap()).is_err());
+        let unknown = json!({ "from": "2024-05-01", "to": "2024-06-01" });
+        assert!(resolve_parameters(&declared, unknown.as_object().unwrap()).is_err());
+        let malformed = json!({ "from": "May 2024" });
+        assert!(resolve_parameters(&declared, malformed.as_object().unwrap()).is_err());
+        assert!(!is_identifier("From"));
+    }
+
+    #[test]
+    fn test_result_rows() {
+        let rows = vec![
+            r#"{"month":"2024-05-01","admissions":12,"ward":null}"#.to_string(),
+            r#"{"month":"2024-06-01","admissions":9,"ward":"ICU"}"#.to_string(),
+        ];
+        let result = ReportResult::from_json_rows(&rows, Vec::new()).unwrap();
+        assert_eq!(result.columns, vec!["month", "admissions", "ward"]);
+        assert_eq!(result.rows[1], vec![json!("2024-06-01"), json!(9), json!("ICU")]);
+        assert!(!result.truncated);
+        assert_eq!(result.to_table().rows[0][2], Cell::Empty);
+    }
+}
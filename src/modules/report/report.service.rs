@@ -0,0 +1,946 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::Query;
+use sqlx::{PgPool, Postgres, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::exporters::pdf::pdf::PdfDocument;
+use crate::modules::authorization::Action;
+use crate::modules::report::report_query::{
+    check_parameters, resolve_parameters, BindValue, CompiledQuery, ReportParameter, ReportQuery, ReportResult,
+    ReportView, ValueType, MAX_ROWS, REPORT_VIEWS,
+};
+use crate::modules::report::report_statement::{check_sql, compile_sql};
+use crate::modules::role::RoleService;
+use crate::modules::tenant::TenantService;
+
+// Import SQL queries
+use crate::modules::report::report_sql::*;
+
+/// Longest a report may run
+const STATEMENT_TIMEOUT: &str = "30s";
+
+/// Whether a report is a query tree or SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportKind {
+    Query,
+    Sql,
+}
+
+impl ReportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportKind::Query => "query",
+            ReportKind::Sql => "sql",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "query" => Some(ReportKind::Query),
+            "sql" => Some(ReportKind::Sql),
+            _ => None,
+        }
+    }
+}
+
+/// What a report's output is given as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    /// Columns and rows as JSON, not kept
+    #[default]
+    Json,
+    Csv,
+    Pdf,
+    Xlsx,
+}
+
+impl ReportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Pdf => "pdf",
+            ReportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "json" => Some(ReportFormat::Json),
+            "csv" => Some(ReportFormat::Csv),
+            "pdf" => Some(ReportFormat::Pdf),
+            "xlsx" => Some(ReportFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "application/json",
+            ReportFormat::Csv => "text/csv; charset=utf-8",
+            ReportFormat::Pdf => "application/pdf",
+            ReportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleFrequency {
+    Daily,
+    /// Each Monday
+    Weekly,
+    /// On the first of each month
+    Monthly,
+}
+
+impl ScheduleFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleFrequency::Daily => "daily",
+            ScheduleFrequency::Weekly => "weekly",
+            ScheduleFrequency::Monthly => "monthly",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "daily" => Some(ScheduleFrequency::Daily),
+            "weekly" => Some(ScheduleFrequency::Weekly),
+            "monthly" => Some(ScheduleFrequency::Monthly),
+            _ => None,
+        }
+    }
+
+    /// First time after `after` a report on this schedule runs, at `hour`
+    /// UTC
+    pub fn next_run(&self, hour: u32, after: DateTime<Utc>) -> DateTime<Utc> {
+        let mut date = after.date_naive();
+        loop {
+            let due = match self {
+                ScheduleFrequency::Daily => true,
+                ScheduleFrequency::Weekly => date.weekday() == Weekday::Mon,
+                ScheduleFrequency::Monthly => date.day() == 1,
+            };
+            if let Some(at) = date.and_hms_opt(hour, 0, 0).map(|at| at.and_utc()) {
+                if due && at > after {
+                    return at;
+                }
+            }
+            date = date.succ_opt().unwrap_or(NaiveDate::MAX);
+        }
+    }
+}
+
+/// When a report runs unattended, and with what
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSchedule {
+    pub frequency: ScheduleFrequency,
+    /// Hour of the day, UTC
+    pub hour: u32,
+    pub format: ReportFormat,
+    /// Values of the report's parameters for its scheduled runs
+    #[serde(default)]
+    pub parameters: Map<String, Value>,
+}
+
+/// A saved report, and the roles whose members may run it
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportDefinition {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: ReportKind,
+    pub query: Option<ReportQuery>,
+    pub sql: Option<String>,
+    pub parameters: Vec<ReportParameter>,
+    pub allowed_roles: Vec<String>,
+    pub schedule: Option<ReportSchedule>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ReportDefinition {
+    /// The report's SQL with the values of its parameters for a run
+    pub fn compile(&self, given: &Map<String, Value>) -> Result<CompiledQuery, String> {
+        let values = resolve_parameters(&self.parameters, given)?;
+        match (&self.query, &self.sql) {
+            (Some(query), _) => query.compile(&values),
+            (None, Some(sql)) => compile_sql(sql, &values),
+            (None, None) => Err(format!("Report {} has no query", self.name)),
+        }
+    }
+}
+
+/// A report to save: either `query`, a query tree over one reporting view,
+/// or `sql`, a query over the reporting views with `:name` parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportDefinitionRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub query: Option<ReportQuery>,
+    pub sql: Option<String>,
+    #[serde(default)]
+    pub parameters: Vec<ReportParameter>,
+    pub allowed_roles: Vec<String>,
+    pub schedule: Option<ReportSchedule>,
+}
+
+impl ReportDefinitionRequest {
+    /// Check the report and put it in its stored form
+    pub fn check(&mut self) -> Result<ReportKind, String> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() {
+            return Err("A report needs a name".to_string());
+        }
+        self.allowed_roles = self.allowed_roles.iter().map(|role| role.trim().to_string()).collect();
+        self.allowed_roles.retain(|role| !role.is_empty());
+        self.allowed_roles.sort();
+        self.allowed_roles.dedup();
+        if self.allowed_roles.is_empty() {
+            return Err("A report needs at least one role allowed to run it".to_string());
+        }
+        check_parameters(&self.parameters)?;
+        let kind = match (&self.query, &mut self.sql) {
+            (Some(query), None) => {
+                query.check(&self.parameters)?;
+                ReportKind::Query
+            }
+            (None, Some(sql)) => {
+                *sql = sql.trim().to_string();
+                check_sql(sql, &self.parameters)?;
+                ReportKind::Sql
+            }
+            _ => return Err("A report is either a query or SQL".to_string()),
+        };
+        if let Some(schedule) = &self.schedule {
+            if schedule.hour > 23 {
+                return Err("A report's scheduled hour is 0 to 23".to_string());
+            }
+            if schedule.format == ReportFormat::Json {
+                return Err("A scheduled report is kept as CSV, PDF or XLSX".to_string());
+            }
+            resolve_parameters(&self.parameters, &schedule.parameters)
+                .map_err(|e| format!("Scheduled parameters: {}", e))?;
+        }
+        Ok(kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunSource {
+    Manual,
+    Scheduled,
+}
+
+impl RunSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunSource::Manual => "manual",
+            RunSource::Scheduled => "scheduled",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "manual" => Some(RunSource::Manual),
+            "scheduled" => Some(RunSource::Scheduled),
+            _ => None,
+        }
+    }
+}
+
+/// A run of a report, without its output
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRun {
+    pub id: Uuid,
+    pub report_id: Uuid,
+    pub source: RunSource,
+    pub parameters: Value,
+    pub format: ReportFormat,
+    /// `succeeded` or `failed`
+    pub status: String,
+    pub row_count: Option<i32>,
+    pub truncated: bool,
+    pub error: Option<String>,
+    pub run_by: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Whether the run's output is kept to be downloaded
+    pub has_output: bool,
+}
+
+/// Parameters and format of a run
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RunRequest {
+    #[serde(default)]
+    pub parameters: Map<String, Value>,
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+/// A report's output as a file
+#[derive(Debug, Clone)]
+pub struct ReportFile {
+    pub content_type: &'static str,
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Output of a run, as asked for
+#[derive(Debug, Clone)]
+pub enum RunOutput {
+    Rows(ReportResult),
+    File(ReportFile),
+}
+
+fn report_definition_from_row(row: &PgRow) -> ReportDefinition {
+    let schedule = match (
+        row.get::<Option<String>, _>("schedule_frequency").as_deref().and_then(ScheduleFrequency::from_string),
+        row.get::<Option<i16>, _>("schedule_hour"),
+        row.get::<Option<String>, _>("schedule_format").as_deref().and_then(ReportFormat::from_string),
+    ) {
+        (Some(frequency), Some(hour), Some(format)) => Some(ReportSchedule {
+            frequency,
+            hour: hour as u32,
+            format,
+            parameters: serde_json::from_value(row.get("schedule_parameters")).unwrap_or_default(),
+        }),
+        _ => None,
+    };
+    ReportDefinition {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        kind: ReportKind::from_string(&row.get::<String, _>("kind")).unwrap_or(ReportKind::Query),
+        query: row
+            .get::<Option<Value>, _>("query")
+            .and_then(|query| serde_json::from_value(query).ok()),
+        sql: row.get("sql_text"),
+        parameters: serde_json::from_value(row.get("parameters")).unwrap_or_default(),
+        allowed_roles: row.get("allowed_roles"),
+        schedule,
+        next_run_at: row.get("next_run_at"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn report_run_from_row(row: &PgRow, has_output: bool) -> ReportRun {
+    ReportRun {
+        id: row.get("id"),
+        report_id: row.get("report_id"),
+        source: RunSource::from_string(&row.get::<String, _>("source")).unwrap_or(RunSource::Manual),
+        parameters: row.get("parameters"),
+        format: ReportFormat::from_string(&row.get::<String, _>("format")).unwrap_or_default(),
+        status: row.get("status"),
+        row_count: row.get("row_count"),
+        truncated: row.get("truncated"),
+        error: row.get("error"),
+        run_by: row.get("run_by"),
+        started_at: row.get("started_at"),
+        finished_at: row.get("finished_at"),
+        has_output,
+    }
+}
+
+/// Bind a value to a report's query
+fn bind_value<'q>(query: Query<'q, Postgres, PgArguments>, value: &BindValue) -> Query<'q, Postgres, PgArguments> {
+    match value.clone() {
+        BindValue::Null(ValueType::Text) => query.bind(None::<String>),
+        BindValue::Null(ValueType::Integer) => query.bind(None::<i64>),
+        BindValue::Null(ValueType::Number) => query.bind(None::<f64>),
+        BindValue::Null(ValueType::Date) => query.bind(None::<NaiveDate>),
+        BindValue::Null(ValueType::Boolean) => query.bind(None::<bool>),
+        BindValue::Text(value) => query.bind(value),
+        BindValue::Integer(value) => query.bind(value),
+        BindValue::Number(value) => query.bind(value),
+        BindValue::Date(value) => query.bind(value),
+        BindValue::Boolean(value) => query.bind(value),
+        BindValue::TextList(values) => query.bind(values),
+        BindValue::IntegerList(values) => query.bind(values),
+        BindValue::NumberList(values) => query.bind(values),
+        BindValue::DateList(values) => query.bind(values),
+    }
+}
+
+/// A report's name as a file name, e.g. `monthly-admissions`
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let stem = stem.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-");
+    if stem.is_empty() {
+        "report".to_string()
+    } else {
+        stem
+    }
+}
+
+/// A report's output in a format, headed in PDF by the report's name and
+/// when and with what it ran
+pub fn render(
+    definition: &ReportDefinition,
+    result: &ReportResult,
+    format: ReportFormat,
+    parameters: &Map<String, Value>,
+    at: DateTime<Utc>,
+) -> ReportFile {
+    let bytes = match format {
+        ReportFormat::Json => serde_json::to_vec(result).unwrap_or_default(),
+        ReportFormat::Csv => result.to_table().to_csv().into_bytes(),
+        ReportFormat::Xlsx => result.to_table().to_xlsx(&definition.name),
+        ReportFormat::Pdf => {
+            let table = result.to_table();
+            let mut document = PdfDocument::new(definition.name.clone());
+            document.heading(definition.name.clone());
+            if let Some(description) = &definition.description {
+                document.paragraph(description.clone());
+            }
+            let mut run = format!("Run {}", at.format("%Y-%m-%d %H:%M UTC"));
+            if !parameters.is_empty() {
+                let given: Vec<String> =
+                    parameters.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+                run.push_str(&format!(" with {}", given.join(", ")));
+            }
+            document.paragraph(run).space();
+            let rows = table.rows.iter().map(|row| row.iter().map(|cell| cell.display()).collect()).collect();
+            document.table(table.columns, rows);
+            if result.truncated {
+                document.space().paragraph(format!("Only the first {} rows are shown.", MAX_ROWS));
+            }
+            document.render()
+        }
+    };
+    ReportFile {
+        content_type: format.content_type(),
+        file_name: format!("{}-{}.{}", file_stem(&definition.name), at.format("%Y%m%d"), format.as_str()),
+        bytes,
+    }
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+/// Errors of a report's own SQL are the report's fault, not the server's
+fn report_error(e: sqlx::Error) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.code().as_deref() == Some("57014") => HimsError::ValidationError {
+            message: format!("The report ran longer than {}", STATEMENT_TIMEOUT),
+        },
+        sqlx::Error::Database(db) => HimsError::ValidationError {
+            message: format!("The report failed: {}", db.message()),
+        },
+        _ => database_error(e),
+    }
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+/// Report service for saved report definitions over the de-identified
+/// reporting views, their runs, and the scheduled runs of those that have a
+/// schedule
+pub struct ReportService {
+    pool: PgPool,
+    role_service: RoleService,
+    tenants: TenantService,
+}
+
+impl ReportService {
+    /// Create new report service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            tenants: TenantService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// The views reports can query
+    pub fn views(&self) -> &'static [ReportView] {
+        REPORT_VIEWS
+    }
+
+    /// Require a permission of the actor
+    async fn require(&self, actor: Uuid, action: Action) -> Result<(), HimsError> {
+        if !self.role_service.get_user_permissions(actor).await?.contains(&action) {
+            tracing::warn!("User {} lacks {} permission", actor, action);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", action),
+            });
+        }
+        Ok(())
+    }
+
+    /// Authors need the generate-report permission; SQL reports, which can
+    /// be written to read any column of the reporting views, also need the
+    /// configure permission
+    async fn require_author(&self, actor: Uuid, kind: ReportKind) -> Result<(), HimsError> {
+        self.require(actor, Action::GenerateReport).await?;
+        if kind == ReportKind::Sql {
+            self.require(actor, Action::Configure).await?;
+        }
+        Ok(())
+    }
+
+    /// Whether the actor holds a role the report may be run by
+    async fn may_run(&self, definition: &ReportDefinition, actor: Uuid) -> Result<bool, HimsError> {
+        let roles = self.role_service.get_user_role_names(actor).await?;
+        Ok(definition.allowed_roles.iter().any(|allowed| roles.contains(allowed)))
+    }
+
+    async fn authorize(&self, definition: &ReportDefinition, actor: Uuid) -> Result<(), HimsError> {
+        if !self.may_run(definition, actor).await? {
+            tracing::warn!("User {} holds no role allowed to run report {}", actor, definition.id);
+            return Err(HimsError::SecurityError {
+                message: format!(
+                    "Report {} may only be run by {}",
+                    definition.name,
+                    definition.allowed_roles.join(", ")
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Save a report
+    pub async fn create_definition(
+        &self,
+        mut request: ReportDefinitionRequest,
+        actor: Uuid,
+    ) -> Result<ReportDefinition, HimsError> {
+        let kind = request.check().map_err(validation_error)?;
+        self.require_author(actor, kind).await?;
+
+        let row = self
+            .bind_definition(sqlx::query(INSERT_REPORT_DEFINITION).bind(Uuid::new_v4()), &request, kind)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("A report named {} already exists", request.name)))?;
+        let definition = report_definition_from_row(&row);
+
+        tracing::info!("Report {} ({}) saved by {}", definition.name, definition.id, actor);
+        Ok(definition)
+    }
+
+    fn bind_definition<'q>(
+        &self,
+        query: Query<'q, Postgres, PgArguments>,
+        request: &ReportDefinitionRequest,
+        kind: ReportKind,
+    ) -> Query<'q, Postgres, PgArguments> {
+        let schedule = request.schedule.as_ref();
+        query
+            .bind(request.name.clone())
+            .bind(request.description.clone())
+            .bind(kind.as_str())
+            .bind(request.query.as_ref().and_then(|query| serde_json::to_value(query).ok()))
+            .bind(request.sql.clone())
+            .bind(serde_json::to_value(&request.parameters).unwrap_or_default())
+            .bind(request.allowed_roles.clone())
+            .bind(schedule.map(|schedule| schedule.frequency.as_str()))
+            .bind(schedule.map(|schedule| schedule.hour as i16))
+            .bind(schedule.map(|schedule| schedule.format.as_str()))
+            .bind(Value::Object(schedule.map(|schedule| schedule.parameters.clone()).unwrap_or_default()))
+            .bind(schedule.map(|schedule| schedule.frequency.next_run(schedule.hour, Utc::now())))
+    }
+
+    /// Replace a report; its schedule starts afresh
+    pub async fn update_definition(
+        &self,
+        id: Uuid,
+        mut request: ReportDefinitionRequest,
+        actor: Uuid,
+    ) -> Result<Option<ReportDefinition>, HimsError> {
+        let kind = request.check().map_err(validation_error)?;
+        let Some(existing) = self.get_definition(id).await? else {
+            return Ok(None);
+        };
+        self.require_author(actor, kind).await?;
+        self.require_author(actor, existing.kind).await?;
+
+        let row = self
+            .bind_definition(sqlx::query(UPDATE_REPORT_DEFINITION).bind(id), &request, kind)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("A report named {} already exists", request.name)))?;
+
+        tracing::info!("Report {} updated by {}", id, actor);
+        Ok(row.as_ref().map(report_definition_from_row))
+    }
+
+    /// Retire a report. Returns false if there was no such report.
+    pub async fn delete_definition(&self, id: Uuid, actor: Uuid) -> Result<bool, HimsError> {
+        let Some(existing) = self.get_definition(id).await? else {
+            return Ok(false);
+        };
+        self.require_author(actor, existing.kind).await?;
+
+        let result = sqlx::query(DEACTIVATE_REPORT_DEFINITION)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        tracing::info!("Report {} retired by {}", id, actor);
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_definition(&self, id: Uuid) -> Result<Option<ReportDefinition>, HimsError> {
+        let row = sqlx::query(GET_REPORT_DEFINITION_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(report_definition_from_row))
+    }
+
+    /// A report the actor may run
+    pub async fn get_definition_for(&self, id: Uuid, actor: Uuid) -> Result<Option<ReportDefinition>, HimsError> {
+        let Some(definition) = self.get_definition(id).await? else {
+            return Ok(None);
+        };
+        self.authorize(&definition, actor).await?;
+        Ok(Some(definition))
+    }
+
+    /// A report, to its authors or those who may run it
+    pub async fn find_definition(&self, id: Uuid, actor: Uuid) -> Result<Option<ReportDefinition>, HimsError> {
+        let Some(definition) = self.get_definition(id).await? else {
+            return Ok(None);
+        };
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        if !permissions.contains(&Action::GenerateReport) {
+            self.authorize(&definition, actor).await?;
+        }
+        Ok(Some(definition))
+    }
+
+    /// Reports: all of them to their authors, otherwise those the actor may
+    /// run
+    pub async fn list_definitions(&self, actor: Uuid) -> Result<Vec<ReportDefinition>, HimsError> {
+        let author = self.role_service.get_user_permissions(actor).await?.contains(&Action::GenerateReport);
+        let roles = self.role_service.get_user_role_names(actor).await?;
+        let rows = sqlx::query(LIST_REPORT_DEFINITIONS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(report_definition_from_row)
+            .filter(|definition| author || definition.allowed_roles.iter().any(|allowed| roles.contains(allowed)))
+            .collect())
+    }
+
+    /// Run a report's SQL in a read-only transaction, limited to the
+    /// reporting schema and the statement timeout
+    async fn execute(&self, compiled: &CompiledQuery) -> Result<ReportResult, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        sqlx::query(SET_READ_ONLY).execute(&mut *tx).await.map_err(database_error)?;
+        sqlx::query(SET_REPORT_LIMITS)
+            .bind(STATEMENT_TIMEOUT)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+
+        // Each row as a JSON object, which keeps its columns' order and
+        // needs no decoding per column type
+        let sql = format!("SELECT row_to_json(r)::text AS row FROM ({}) r LIMIT {}", compiled.sql, MAX_ROWS + 1);
+        let query = compiled.binds.iter().fold(sqlx::query(&sql), bind_value);
+        let rows: Vec<String> = query
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(report_error)?
+            .iter()
+            .map(|row| row.get("row"))
+            .collect();
+        tx.rollback().await.map_err(database_error)?;
+
+        ReportResult::from_json_rows(&rows, compiled.columns.clone())
+            .map_err(|message| HimsError::InternalError { message })
+    }
+
+    /// Record a run of a report
+    #[allow(clippy::too_many_arguments)]
+    async fn record_run(
+        &self,
+        definition: &ReportDefinition,
+        source: RunSource,
+        parameters: &Map<String, Value>,
+        format: ReportFormat,
+        outcome: &Result<ReportResult, HimsError>,
+        output: Option<Vec<u8>>,
+        run_by: Option<Uuid>,
+        started_at: DateTime<Utc>,
+    ) -> Result<ReportRun, HimsError> {
+        let has_output = output.is_some();
+        let row = sqlx::query(INSERT_REPORT_RUN)
+            .bind(Uuid::new_v4())
+            .bind(definition.id)
+            .bind(source.as_str())
+            .bind(Value::Object(parameters.clone()))
+            .bind(format.as_str())
+            .bind(if outcome.is_ok() { "succeeded" } else { "failed" })
+            .bind(outcome.as_ref().ok().map(|result| result.rows.len() as i32))
+            .bind(outcome.as_ref().is_ok_and(|result| result.truncated))
+            .bind(output)
+            .bind(outcome.as_ref().err().map(|e| e.to_string()))
+            .bind(run_by)
+            .bind(started_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(report_run_from_row(&row, has_output))
+    }
+
+    /// Run a report the actor may run, with parameters, in a format. The
+    /// run is recorded; its output is not kept.
+    pub async fn run(&self, id: Uuid, request: RunRequest, actor: Uuid) -> Result<Option<RunOutput>, HimsError> {
+        let Some(definition) = self.get_definition_for(id, actor).await? else {
+            return Ok(None);
+        };
+        let started_at = Utc::now();
+        let compiled = definition.compile(&request.parameters).map_err(validation_error)?;
+
+        let outcome = self.execute(&compiled).await;
+        self.record_run(
+            &definition,
+            RunSource::Manual,
+            &request.parameters,
+            request.format,
+            &outcome,
+            None,
+            Some(actor),
+            started_at,
+        )
+        .await?;
+        let result = outcome?;
+
+        tracing::info!("Report {} run by {}: {} rows", definition.id, actor, result.rows.len());
+        Ok(Some(match request.format {
+            ReportFormat::Json => RunOutput::Rows(result),
+            format => RunOutput::File(render(&definition, &result, format, &request.parameters, started_at)),
+        }))
+    }
+
+    /// Latest runs of a report the actor may run
+    pub async fn list_runs(&self, id: Uuid, actor: Uuid) -> Result<Option<Vec<ReportRun>>, HimsError> {
+        if self.get_definition_for(id, actor).await?.is_none() {
+            return Ok(None);
+        }
+        let rows = sqlx::query(LIST_REPORT_RUNS)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(Some(
+            rows.iter()
+                .map(|row| {
+                    let kept = row.get::<String, _>("source") == RunSource::Scheduled.as_str()
+                        && row.get::<String, _>("status") == "succeeded";
+                    report_run_from_row(row, kept)
+                })
+                .collect(),
+        ))
+    }
+
+    /// The kept output of a scheduled run of a report the actor may run
+    pub async fn run_output(&self, id: Uuid, run_id: Uuid, actor: Uuid) -> Result<Option<ReportFile>, HimsError> {
+        let Some(definition) = self.get_definition_for(id, actor).await? else {
+            return Ok(None);
+        };
+        let row = sqlx::query(GET_REPORT_RUN_OUTPUT)
+            .bind(run_id)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.map(|row| {
+            let format = ReportFormat::from_string(&row.get::<String, _>("format")).unwrap_or_default();
+            let started_at: DateTime<Utc> = row.get("started_at");
+            ReportFile {
+                content_type: format.content_type(),
+                file_name: format!(
+                    "{}-{}.{}",
+                    file_stem(&definition.name),
+                    started_at.format("%Y%m%d"),
+                    format.as_str()
+                ),
+                bytes: row.get("output"),
+            }
+        }))
+    }
+
+    /// Run a scheduled report as its tenant and keep its output
+    async fn run_scheduled(
+        &self,
+        definition: &ReportDefinition,
+        schedule: &ReportSchedule,
+    ) -> Result<ReportRun, HimsError> {
+        let started_at = Utc::now();
+        let outcome = match definition.compile(&schedule.parameters) {
+            Ok(compiled) => self.execute(&compiled).await,
+            Err(message) => Err(validation_error(message)),
+        };
+        let output = outcome
+            .as_ref()
+            .ok()
+            .map(|result| render(definition, result, schedule.format, &schedule.parameters, started_at).bytes);
+        self.record_run(
+            definition,
+            RunSource::Scheduled,
+            &schedule.parameters,
+            schedule.format,
+            &outcome,
+            output,
+            None,
+            started_at,
+        )
+        .await
+    }
+
+    /// Run the scheduled reports that are due, across tenants. Each is
+    /// moved to its next time first, so a report that fails is not retried
+    /// until then. Returns how many ran.
+    pub async fn run_due(&self) -> Result<usize, HimsError> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let rows = sqlx::query(CLAIM_DUE_REPORTS).fetch_all(&mut *tx).await.map_err(database_error)?;
+        let mut due = Vec::new();
+        for row in &rows {
+            let definition = report_definition_from_row(row);
+            let next = definition.schedule.as_ref().map(|schedule| schedule.frequency.next_run(schedule.hour, now));
+            sqlx::query(SET_NEXT_RUN)
+                .bind(definition.id)
+                .bind(next)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+            due.push((row.get::<Uuid, _>("tenant_id"), definition));
+        }
+        tx.commit().await.map_err(database_error)?;
+
+        for (tenant_id, definition) in &due {
+            let Some(schedule) = &definition.schedule else {
+                continue;
+            };
+            let context = match self.tenants.get_tenant(*tenant_id).await? {
+                Some(tenant) => tenant.context(),
+                None => TenantContext::new(*tenant_id, None),
+            };
+            match with_tenant(context, self.run_scheduled(definition, schedule)).await {
+                Ok(run) if run.status == "succeeded" => {
+                    tracing::info!("Scheduled report {} ran: {} rows", definition.id, run.row_count.unwrap_or(0));
+                }
+                Ok(run) => tracing::warn!(
+                    "Scheduled report {} failed: {}",
+                    definition.id,
+                    run.error.unwrap_or_default()
+                ),
+                Err(e) => tracing::error!("Failed to record scheduled run of report {}: {}", definition.id, e),
+            }
+        }
+        Ok(due.len())
+    }
+
+    /// Run scheduled reports as they fall due, checking each `interval`
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_due().await {
+                    tracing::error!("Failed to run scheduled reports: {}", e);
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_next_run() {
+        let at = |text: &str| DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc);
+        // 2024-05-15 is a Wednesday
+        let after = at("2024-05-15T09:30:00Z");
+        assert_eq!(ScheduleFrequency::Daily.next_run(6, after), at("2024-05-16T06:00:00Z"));
+        assert_eq!(ScheduleFrequency::Daily.next_run(10, after), at("2024-05-15T10:00:00Z"));
+        assert_eq!(ScheduleFrequency::Weekly.next_run(6, after), at("2024-05-20T06:00:00Z"));
+        assert_eq!(ScheduleFrequency::Monthly.next_run(0, after), at("2024-06-01T00:00:00Z"));
+        assert_eq!(ScheduleFrequency::Monthly.next_run(0, at("2024-06-01T00:00:00Z")), at("2024-07-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_definition_request_check() {
+        let request = || -> ReportDefinitionRequest {
+            serde_json::from_value(json!({
+                "name": " Monthly admissions ",
+                "query": {
+                    "source": "encounters",
+                    "select": [{ "column": "start_month" }, { "aggregate": "count", "alias": "admissions" }],
+                    "filters": [{ "column": "start_month", "op": "ge", "value": { "param": "from" } }],
+                    "group_by": ["start_month"],
+                },
+                "parameters": [{ "name": "from", "type": "date", "required": true }],
+                "allowed_roles": ["Quality", " quality", "Quality"],
+                "schedule": {
+                    "frequency": "monthly",
+                    "hour": 2,
+                    "format": "xlsx",
+                    "parameters": { "from": "2024-01-01" },
+                },
+            }))
+            .unwrap()
+        };
+        let mut checked = request();
+        assert_eq!(checked.check(), Ok(ReportKind::Query));
+        assert_eq!(checked.name, "Monthly admissions");
+        assert_eq!(checked.allowed_roles, vec!["Quality", "quality"]);
+
+        let mut both = request();
+        both.sql = Some("SELECT 1".to_string());
+        assert!(both.check().is_err());
+        let mut no_roles = ReportDefinitionRequest { allowed_roles: vec![" ".to_string()], ..request() };
+        assert!(no_roles.check().is_err());
+        let mut unscheduled_parameter = request();
+        unscheduled_parameter.schedule.as_mut().unwrap().parameters.clear();
+        assert!(unscheduled_parameter.check().is_err(), "a scheduled run would lack the required parameter");
+        let mut sql = ReportDefinitionRequest {
+            query: None,
+            sql: Some(" SELECT count(*) AS n FROM encounters WHERE start_month >= :from ".to_string()),
+            ..request()
+        };
+        assert_eq!(sql.check(), Ok(ReportKind::Sql));
+        assert_eq!(file_stem("Monthly admissions (ICU)"), "monthly-admissions-icu");
+    }
+}
@@ -0,0 +1,101 @@
+//! Report SQL Queries
+//!
+//! This file contains all SQL queries used by the report service.
+
+/// Insert a report definition
+pub const INSERT_REPORT_DEFINITION: &str = r#"
+    INSERT INTO report_definitions (
+        id, name, description, kind, query, sql_text, parameters, allowed_roles, schedule_frequency,
+        schedule_hour, schedule_format, schedule_parameters, next_run_at, created_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+    RETURNING id, name, description, kind, query, sql_text, parameters, allowed_roles, schedule_frequency,
+              schedule_hour, schedule_format, schedule_parameters, next_run_at, created_by, created_at, updated_at
+"#;
+
+/// Get an active report definition by ID
+pub const GET_REPORT_DEFINITION_BY_ID: &str = r#"
+    SELECT id, name, description, kind, query, sql_text, parameters, allowed_roles, schedule_frequency,
+           schedule_hour, schedule_format, schedule_parameters, next_run_at, created_by, created_at, updated_at
+    FROM report_definitions
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Active report definitions, by name
+pub const LIST_REPORT_DEFINITIONS: &str = r#"
+    SELECT id, name, description, kind, query, sql_text, parameters, allowed_roles, schedule_frequency,
+           schedule_hour, schedule_format, schedule_parameters, next_run_at, created_by, created_at, updated_at
+    FROM report_definitions
+    WHERE is_active = true
+    ORDER BY name
+"#;
+
+/// Replace an active report definition
+pub const UPDATE_REPORT_DEFINITION: &str = r#"
+    UPDATE report_definitions
+    SET name = $2, description = $3, kind = $4, query = $5, sql_text = $6, parameters = $7, allowed_roles = $8,
+        schedule_frequency = $9, schedule_hour = $10, schedule_format = $11, schedule_parameters = $12,
+        next_run_at = $13
+    WHERE id = $1 AND is_active = true
+    RETURNING id, name, description, kind, query, sql_text, parameters, allowed_roles, schedule_frequency,
+              schedule_hour, schedule_format, schedule_parameters, next_run_at, created_by, created_at, updated_at
+"#;
+
+/// Retire a report definition, keeping its runs
+pub const DEACTIVATE_REPORT_DEFINITION: &str = r#"
+    UPDATE report_definitions
+    SET is_active = false, next_run_at = NULL
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Scheduled reports due to run, locked so one server runs each
+pub const CLAIM_DUE_REPORTS: &str = r#"
+    SELECT tenant_id, id, name, description, kind, query, sql_text, parameters, allowed_roles, schedule_frequency,
+           schedule_hour, schedule_format, schedule_parameters, next_run_at, created_by, created_at, updated_at
+    FROM report_definitions
+    WHERE is_active = true AND next_run_at <= NOW()
+    ORDER BY next_run_at
+    LIMIT 20
+    FOR UPDATE SKIP LOCKED
+"#;
+
+/// Set when a scheduled report next runs
+pub const SET_NEXT_RUN: &str = r#"
+    UPDATE report_definitions
+    SET next_run_at = $2
+    WHERE id = $1
+"#;
+
+/// Make the transaction a report runs in read-only
+pub const SET_READ_ONLY: &str = "SET TRANSACTION READ ONLY";
+
+/// Limit a report to the reporting schema and a statement timeout, for the
+/// rest of its transaction
+pub const SET_REPORT_LIMITS: &str = r#"
+    SELECT set_config('search_path', 'reporting', true), set_config('statement_timeout', $1, true)
+"#;
+
+/// Record a run of a report
+pub const INSERT_REPORT_RUN: &str = r#"
+    INSERT INTO report_runs (
+        id, report_id, source, parameters, format, status, row_count, truncated, output, error, run_by, started_at
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+    RETURNING id, report_id, source, parameters, format, status, row_count, truncated, error, run_by,
+              started_at, finished_at
+"#;
+
+/// The 100 latest runs of a report
+pub const LIST_REPORT_RUNS: &str = r#"
+    SELECT id, report_id, source, parameters, format, status, row_count, truncated, error, run_by,
+           started_at, finished_at
+    FROM report_runs
+    WHERE report_id = $1
+    ORDER BY started_at DESC
+    LIMIT 100
+"#;
+
+/// A run of a report with its kept output
+pub const GET_REPORT_RUN_OUTPUT: &str = r#"
+    SELECT format, output, started_at
+    FROM report_runs
+    WHERE id = $1 AND report_id = $2 AND output IS NOT NULL
+"#;
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::modules::report::report_query::{report_view, BindValue, CompiledQuery, ReportParameter};
+
+/// Words that only statements other than a query use, or that make a
+/// query write
+const FORBIDDEN_WORDS: &[&str] = &[
+    "alter", "analyze", "begin", "call", "checkpoint", "cluster", "comment", "commit", "copy", "create", "deallocate",
+    "declare", "delete", "discard", "do", "drop", "execute", "explain", "grant", "import", "insert", "into", "listen",
+    "load", "lock", "merge", "notify", "prepare", "refresh", "reindex", "release", "reset", "revoke", "rollback",
+    "savepoint", "security", "set", "show", "table", "truncate", "unlisten", "update", "vacuum",
+];
+
+/// Keywords a parenthesis may follow
+const KEYWORDS_BEFORE_PAREN: &[&str] = &[
+    "all", "and", "any", "array", "as", "between", "by", "case", "cube", "distinct", "else", "except", "exists",
+    "filter", "from", "grouping", "having", "in", "intersect", "join", "lateral", "limit", "materialized", "not",
+    "offset", "on", "or", "over", "rollup", "row", "select", "sets", "some", "then", "union", "using", "values",
+    "when", "where", "with", "within",
+];
+
+/// Functions report SQL may call: aggregates, window functions and pure
+/// functions of their arguments. Nothing that reads settings, other
+/// relations or files, or has side effects.
+const FUNCTIONS: &[&str] = &[
+    "abs", "age", "array_agg", "avg", "bool_and", "bool_or", "cast", "ceil", "ceiling", "coalesce", "concat", "count",
+    "date_part", "date_trunc", "dense_rank", "extract", "first_value", "floor", "generate_series", "greatest", "lag",
+    "last_value", "lead", "least", "length", "lower", "make_date", "max", "min", "mode", "nullif", "ntile",
+    "percent_rank", "percentile_cont", "percentile_disc", "position", "rank", "replace", "round", "row_number",
+    "split_part", "stddev", "string_agg", "substring", "sum", "to_char", "trim", "trunc", "unnest", "upper",
+    "variance",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An unquoted word, lower-cased
+    Word(String),
+    /// A quoted identifier
+    Quoted(String),
+    /// `:name`
+    Parameter(String),
+    /// `::`
+    Cast,
+    Symbol(char),
+    /// A string or number literal
+    Literal,
+}
+
+/// Tokens of SQL with where each is in it
+fn tokenize(sql: &str) -> Result<Vec<(Token, Range<usize>)>, String> {
+    let chars: Vec<(usize, char)> = sql.char_indices().collect();
+    let at = |i: usize| chars.get(i).map(|(_, c)| *c);
+    let offset = |i: usize| chars.get(i).map_or(sql.len(), |(offset, _)| *offset);
+    let word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+
+    let mut tokens: Vec<(Token, Range<usize>)> = Vec::new();
+    let mut i = 0;
+    while let Some(c) = at(i) {
+        let start = i;
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '-' if at(i + 1) == Some('-') => return Err("Report SQL cannot have comments".to_string()),
+            '/' if at(i + 1) == Some('*') => return Err("Report SQL cannot have comments".to_string()),
+            '$' => return Err("Report SQL cannot use $ quoting or placeholders; use :name parameters".to_string()),
+            '\'' => {
+                // E'', U&'' and similar strings have escapes of their own
+                if tokens.last().is_some_and(|(token, range)| {
+                    range.end == offset(i) && matches!(token, Token::Word(_) | Token::Symbol('&'))
+                }) {
+                    return Err("Report SQL cannot use prefixed string literals".to_string());
+                }
+                i += 1;
+                loop {
+                    match at(i) {
+                        Some('\'') if at(i + 1) == Some('\'') => i += 2,
+                        Some('\'') => break,
+                        Some(_) => i += 1,
+                        None => return Err("Report SQL has an unterminated string".to_string()),
+                    }
+                }
+                i += 1;
+                Token::Literal
+            }
+            '"' => {
+                let mut identifier = String::new();
+                i += 1;
+                loop {
+                    match at(i) {
+                        Some('"') if at(i + 1) == Some('"') => {
+                            identifier.push('"');
+                            i += 2;
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            identifier.push(c);
+                            i += 1;
+                        }
+                        None => return Err("Report SQL has an unterminated identifier".to_string()),
+                    }
+                }
+                i += 1;
+                Token::Quoted(identifier)
+            }
+            ':' if at(i + 1) == Some(':') => {
+                i += 2;
+                Token::Cast
+            }
+            ':' if at(i + 1).is_some_and(|c| c.is_ascii_alphabetic() || c == '_') => {
+                i += 1;
+                while at(i).is_some_and(word_char) {
+                    i += 1;
+                }
+                Token::Parameter(sql[offset(start + 1)..offset(i)].to_string())
+            }
+            c if c.is_ascii_digit() => {
+                while at(i).is_some_and(|c| c.is_ascii_alphanumeric() || c == '.') {
+                    i += 1;
+                }
+                Token::Literal
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while at(i).is_some_and(word_char) {
+                    i += 1;
+                }
+                Token::Word(sql[offset(start)..offset(i)].to_lowercase())
+            }
+            c => {
+                i += 1;
+                Token::Symbol(c)
+            }
+        };
+        tokens.push((token, offset(start)..offset(i)));
+    }
+    Ok(tokens)
+}
+
+/// Check a relation a query reads, at `tokens[0]`: a reporting view, or a
+/// common table expression the query defines. Returns how many tokens
+/// name it.
+fn check_relation(tokens: &[Token], expressions: &[String]) -> Result<usize, String> {
+    let mut parts = Vec::new();
+    let mut count = 0;
+    while let Some(Token::Word(part) | Token::Quoted(part)) = tokens.get(count) {
+        parts.push(part.as_str());
+        count += 1;
+        if tokens.get(count) != Some(&Token::Symbol('.')) {
+            break;
+        }
+        count += 1;
+    }
+    if tokens.get(count) == Some(&Token::Symbol('(')) {
+        // A set-returning function, which the function check covers
+        return Ok(count);
+    }
+    let allowed = match parts.as_slice() {
+        [name] => report_view(name).is_some() || expressions.iter().any(|expression| expression == name),
+        ["reporting", name] => report_view(name).is_some(),
+        _ => false,
+    };
+    if allowed {
+        Ok(count)
+    } else {
+        Err(format!("{} is not a reporting view", parts.join(".")))
+    }
+}
+
+/// Check SQL is a single query that reads only the reporting views and
+/// calls only known functions, and replace its `:name` parameters with
+/// placeholders. Returns the SQL and the parameter each placeholder takes.
+///
+/// SQL that passes is still run in a read-only transaction, with a
+/// statement timeout and only the reporting schema on its search path.
+pub fn rewrite_sql(sql: &str) -> Result<(String, Vec<String>), String> {
+    let mut tokens = tokenize(sql)?;
+    if matches!(tokens.last(), Some((Token::Symbol(';'), _))) {
+        tokens.pop();
+    }
+    let words: Vec<Token> = tokens.iter().map(|(token, _)| token.clone()).collect();
+
+    if !matches!(words.first(), Some(Token::Word(word)) if word == "select" || word == "with") {
+        return Err("Report SQL must be a query, starting with SELECT or WITH".to_string());
+    }
+    let mut depth = 0usize;
+    for (index, token) in words.iter().enumerate() {
+        match token {
+            Token::Symbol(';') => return Err("Report SQL must be a single statement".to_string()),
+            Token::Symbol('(') => depth += 1,
+            Token::Symbol(')') => {
+                depth = depth.checked_sub(1).ok_or("Report SQL has unbalanced parentheses")?;
+            }
+            Token::Word(word) if FORBIDDEN_WORDS.contains(&word.as_str()) => {
+                return Err(format!("Report SQL cannot use {}", word.to_uppercase()));
+            }
+            Token::Word(_) | Token::Quoted(_) if words.get(index + 1) == Some(&Token::Symbol('(')) => {
+                // Type names, as in CAST(x AS numeric(10, 2)) or x::varchar(20)
+                let type_name = index > 0
+                    && match &words[index - 1] {
+                        Token::Cast => true,
+                        Token::Word(word) => word == "as",
+                        _ => false,
+                    };
+                match token {
+                    Token::Word(word)
+                        if type_name
+                            || KEYWORDS_BEFORE_PAREN.contains(&word.as_str())
+                            || FUNCTIONS.contains(&word.as_str()) => {}
+                    Token::Word(word) => return Err(format!("Report SQL cannot call {}", word)),
+                    _ => return Err("Report SQL cannot call quoted function names".to_string()),
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err("Report SQL has unbalanced parentheses".to_string());
+    }
+
+    // Names of common table expressions, `name AS (` or `name AS [NOT]
+    // MATERIALIZED (`. None may take the name of a system catalog, which
+    // would be read wherever the expression is out of scope.
+    let opens_expression = |token: &Token| match token {
+        Token::Symbol('(') => true,
+        Token::Word(word) => word == "materialized" || word == "not",
+        _ => false,
+    };
+    let expressions: Vec<String> = words
+        .windows(3)
+        .filter_map(|window| match window {
+            [Token::Word(name) | Token::Quoted(name), Token::Word(as_), next]
+                if as_ == "as" && opens_expression(next) =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    if let Some(name) = expressions.iter().find(|name| name.to_lowercase().starts_with("pg_")) {
+        return Err(format!("{} cannot name a common table expression", name));
+    }
+
+    // Relations follow FROM, JOIN and the commas of a FROM list, in each
+    // parenthesised query
+    struct Scope {
+        query: bool,
+        from: bool,
+    }
+    let mut scopes = vec![Scope { query: true, from: false }];
+    let mut expect_relation = false;
+    let mut index = 0;
+    while index < words.len() {
+        let scope = scopes.last_mut().ok_or("Report SQL has unbalanced parentheses")?;
+        match &words[index] {
+            Token::Symbol('(') => {
+                let subquery = matches!(
+                    words.get(index + 1),
+                    Some(Token::Word(word)) if ["select", "with", "values"].contains(&word.as_str())
+                );
+                // A parenthesised FROM list, as in FROM (a JOIN b ON ...)
+                let from = expect_relation && !subquery;
+                scopes.push(Scope { query: subquery || from, from });
+                expect_relation = from;
+            }
+            Token::Symbol(')') => {
+                scopes.pop();
+                expect_relation = false;
+            }
+            Token::Symbol(',') if scope.query && scope.from => expect_relation = true,
+            Token::Word(word) if scope.query && ["from", "join"].contains(&word.as_str()) => {
+                scope.from = true;
+                expect_relation = true;
+            }
+            Token::Word(word)
+                if scope.query
+                    && [
+                        "where", "group", "having", "order", "limit", "offset", "union", "intersect", "except",
+                        "window", "fetch", "select",
+                    ]
+                    .contains(&word.as_str()) =>
+            {
+                scope.from = false;
+                expect_relation = false;
+            }
+            Token::Word(word) if expect_relation && (word == "lateral" || word == "only") => {}
+            Token::Word(_) | Token::Quoted(_) if expect_relation => {
+                index += check_relation(&words[index..], &expressions)?;
+                expect_relation = false;
+                continue;
+            }
+            _ => expect_relation = false,
+        }
+        index += 1;
+    }
+
+    // Each parameter takes the placeholder of its first use
+    let mut names: Vec<String> = Vec::new();
+    let mut rewritten = String::with_capacity(sql.len());
+    let mut copied = 0;
+    for (token, range) in &tokens {
+        if let Token::Parameter(name) = token {
+            let position = match names.iter().position(|known| known == name) {
+                Some(position) => position,
+                None => {
+                    names.push(name.clone());
+                    names.len() - 1
+                }
+            };
+            rewritten.push_str(&sql[copied..range.start]);
+            rewritten.push_str(&format!("${}", position + 1));
+            copied = range.end;
+        }
+    }
+    let end = tokens.last().map_or(0, |(_, range)| range.end);
+    rewritten.push_str(&sql[copied..end]);
+    Ok((rewritten, names))
+}
+
+/// Check report SQL and that each parameter it uses is declared
+pub fn check_sql(sql: &str, declared: &[ReportParameter]) -> Result<(), String> {
+    let (_, names) = rewrite_sql(sql)?;
+    match names.iter().find(|name| !declared.iter().any(|parameter| &parameter.name == *name)) {
+        Some(name) => Err(format!("Report SQL uses :{}, which is not a declared parameter", name)),
+        None => Ok(()),
+    }
+}
+
+/// Report SQL with parameters' values bound to its placeholders
+pub fn compile_sql(sql: &str, parameters: &HashMap<String, BindValue>) -> Result<CompiledQuery, String> {
+    let (sql, names) = rewrite_sql(sql)?;
+    let binds = names
+        .iter()
+        .map(|name| {
+            parameters
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("Report SQL uses :{}, which is not a declared parameter", name))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(CompiledQuery {
+        sql,
+        binds,
+        columns: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_sql() {
+        let (sql, names) = rewrite_sql(
+            "WITH monthly AS (SELECT start_month, count(*) AS n FROM reporting.encounters e \
+             WHERE e.start_month >= :from AND e.class = 'IMP' GROUP BY 1) \
+             SELECT m.start_month, m.n, extract(year FROM m.start_month)::int AS year, p.age_band \
+             FROM monthly m, patients p JOIN (reporting.charges c JOIN conditions d ON true) ON true \
+             WHERE m.start_month < :to OR m.start_month = :from::date ORDER BY 1;",
+        )
+        .unwrap();
+        assert_eq!(names, vec!["from", "to"]);
+        assert!(sql.contains("e.start_month >= $1 AND e.class = 'IMP'"));
+        assert!(sql.ends_with("m.start_month < $2 OR m.start_month = $1::date ORDER BY 1"));
+
+        let rejected = |sql: &str| rewrite_sql(sql).is_err();
+        assert!(rejected("DELETE FROM reporting.patients"));
+        assert!(rejected("SELECT * FROM patients; DROP TABLE patients"));
+        assert!(rejected("SELECT * FROM public.patients"));
+        assert!(rejected("SELECT * FROM users"));
+        assert!(rejected("SELECT * FROM reporting.patients, users"));
+        assert!(rejected("SELECT * FROM (users JOIN roles ON true)"));
+        assert!(rejected("SELECT * FROM patients WHERE gender IN (SELECT name FROM \"public\".\"users\")"));
+        assert!(rejected("SELECT set_config('app.bypass_tenant', 'on', true)"));
+        assert!(rejected("SELECT pg_catalog.current_setting('app.tenant_id')"));
+        assert!(rejected("SELECT * FROM patients -- comment"));
+        assert!(rejected("SELECT $1"));
+        assert!(rejected("SELECT E'\\'' FROM patients"));
+        assert!(rejected("SELECT 1) r, users (x"));
+        assert!(rejected("SELECT * INTO copy FROM patients"));
+        assert!(rejected("SELECT 'unterminated FROM patients"));
+
+        let declared: Vec<ReportParameter> =
+            serde_json::from_value(serde_json::json!([{ "name": "from", "type": "date" }])).unwrap();
+        assert!(check_sql("SELECT count(*) FROM encounters WHERE start_month >= :from", &declared).is_ok());
+        assert!(check_sql("SELECT count(*) FROM encounters WHERE start_month < :to", &declared).is_err());
+    }
+}
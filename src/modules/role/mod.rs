@@ -0,0 +1,52 @@
+//! Role Module
+//! 
+//! This module provides role and permission administration including:
+//! - Role CRUD operations
+//! - Permission bundles mapped to authorization actions
+//! - Role membership management
+//! - Relationship grants for `Subject::Role` tuples
+//! - Privilege escalation protection
+
+#[path = "role.controller.rs"]
+pub mod role_controller;
+#[path = "role.service.rs"]
+pub mod role_service;
+#[path = "role.sql.rs"]
+pub mod role_sql;
+
+pub use role_controller::RoleController;
+pub use role_service::RoleService;
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Role Module Configuration
+pub struct RoleModule {
+    pub service: Arc<RoleService>,
+    pub controller: Arc<RoleController>,
+}
+
+impl RoleModule {
+    /// Create a new Role Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(RoleService::new(db_pool));
+        let controller = Arc::new(RoleController::new(service.clone()));
+        
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<RoleService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,323 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::{Action, HealthcareRelation, RelationshipTuple, Resource};
+use crate::modules::role::role_service::{Role, RoleMember};
+use crate::modules::role::RoleService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Role controller for role and permission administration
+pub struct RoleController {
+    role_service: Arc<RoleService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleQuery {
+    pub _count: Option<i64>,
+    pub _offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Action names, e.g. "read", "prescribe", "manage_users"
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub is_system: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleMemberRequest {
+    pub user_id: Uuid,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleGrantRequest {
+    pub resource: Resource,
+    pub relation: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleGrantResponse {
+    pub resource: String,
+    pub relation: String,
+    pub subject: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl RoleController {
+    /// Create new controller with injected service
+    pub fn new(role_service: Arc<RoleService>) -> Self {
+        Self { role_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_role, "Create new role")
+            .get("/", Self::list_roles, "List roles")
+            .get("/:id", Self::get_role, "Get role by ID")
+            .put("/:id", Self::update_role, "Update role")
+            .delete("/:id", Self::delete_role, "Delete role")
+            .get("/:id/members", Self::get_members, "List role members")
+            .post("/:id/members", Self::add_member, "Assign a user to a role")
+            .delete("/:id/members/:user_id", Self::remove_member, "Remove a user from a role")
+            .post("/:id/grants", Self::grant_relationship, "Grant a role a relationship on a resource")
+            .delete("/:id/grants", Self::revoke_relationship, "Revoke a relationship from a role")
+            .with_state(self.role_service.clone())
+    }
+
+    /// Create new role
+    pub async fn create_role(
+        State(role_service): State<Arc<RoleService>>,
+        headers: HeaderMap,
+        Json(payload): Json<RoleRequest>,
+    ) -> Result<(StatusCode, Json<RoleResponse>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Creating role {}", payload.name);
+
+        let permissions = Self::parse_permissions(&payload.permissions);
+        match role_service.create_role(actor, &payload.name, payload.description, permissions).await {
+            Ok(role) => Ok((StatusCode::CREATED, Json(Self::role_to_response(role)))),
+            Err(e) => Err(Self::error_response("Failed to create role", e)),
+        }
+    }
+
+    /// List roles
+    pub async fn list_roles(
+        State(role_service): State<Arc<RoleService>>,
+        Query(params): Query<RoleQuery>,
+    ) -> Result<Json<Vec<RoleResponse>>, (StatusCode, Json<ErrorResponse>)> {
+        match role_service.list_roles(params._count, params._offset).await {
+            Ok(roles) => Ok(Json(roles.into_iter().map(Self::role_to_response).collect())),
+            Err(e) => Err(Self::error_response("Failed to list roles", e)),
+        }
+    }
+
+    /// Get role by ID
+    pub async fn get_role(
+        State(role_service): State<Arc<RoleService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
+        match role_service.get_role(id).await {
+            Ok(Some(role)) => Ok(Json(Self::role_to_response(role))),
+            Ok(None) => {
+                tracing::warn!("Role not found: {}", id);
+                Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Role not found".to_string(),
+                        message: format!("Role with id {} not found", id),
+                    }),
+                ))
+            }
+            Err(e) => Err(Self::error_response("Failed to retrieve role", e)),
+        }
+    }
+
+    /// Update role
+    pub async fn update_role(
+        State(role_service): State<Arc<RoleService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RoleRequest>,
+    ) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Updating role: {}", id);
+
+        let permissions = Self::parse_permissions(&payload.permissions);
+        match role_service.update_role(actor, id, &payload.name, payload.description, permissions).await {
+            Ok(role) => Ok(Json(Self::role_to_response(role))),
+            Err(e) => Err(Self::error_response("Failed to update role", e)),
+        }
+    }
+
+    /// Delete role
+    pub async fn delete_role(
+        State(role_service): State<Arc<RoleService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Deleting role: {}", id);
+
+        match role_service.delete_role(actor, id).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to delete role", e)),
+        }
+    }
+
+    /// List role members
+    pub async fn get_members(
+        State(role_service): State<Arc<RoleService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<RoleMember>>, (StatusCode, Json<ErrorResponse>)> {
+        match role_service.get_members(id).await {
+            Ok(members) => Ok(Json(members)),
+            Err(e) => Err(Self::error_response("Failed to list role members", e)),
+        }
+    }
+
+    /// Assign a user to a role
+    pub async fn add_member(
+        State(role_service): State<Arc<RoleService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RoleMemberRequest>,
+    ) -> Result<(StatusCode, Json<RoleMember>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Assigning user {} to role {}", payload.user_id, id);
+
+        match role_service.add_member(actor, id, payload.user_id, payload.expires_at).await {
+            Ok(member) => Ok((StatusCode::CREATED, Json(member))),
+            Err(e) => Err(Self::error_response("Failed to assign role", e)),
+        }
+    }
+
+    /// Remove a user from a role
+    pub async fn remove_member(
+        State(role_service): State<Arc<RoleService>>,
+        headers: HeaderMap,
+        Path((id, user_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Removing user {} from role {}", user_id, id);
+
+        match role_service.remove_member(actor, id, user_id).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to remove role member", e)),
+        }
+    }
+
+    /// Grant a role a relationship on a resource
+    pub async fn grant_relationship(
+        State(role_service): State<Arc<RoleService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RoleGrantRequest>,
+    ) -> Result<(StatusCode, Json<RoleGrantResponse>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        let relation = Self::parse_relation(&payload.relation);
+        tracing::info!("Granting {} on {} to role {}", relation, payload.resource, id);
+
+        match role_service.grant_relationship(actor, id, payload.resource, relation, payload.expires_at).await {
+            Ok(tuple) => Ok((StatusCode::CREATED, Json(Self::tuple_to_response(tuple)))),
+            Err(e) => Err(Self::error_response("Failed to grant relationship", e)),
+        }
+    }
+
+    /// Revoke a relationship from a role
+    pub async fn revoke_relationship(
+        State(role_service): State<Arc<RoleService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RoleGrantRequest>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        let relation = Self::parse_relation(&payload.relation);
+        tracing::info!("Revoking {} on {} from role {}", relation, payload.resource, id);
+
+        match role_service.revoke_relationship(actor, id, payload.resource, relation).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to revoke relationship", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn parse_permissions(permissions: &[String]) -> Vec<Action> {
+        permissions
+            .iter()
+            .filter_map(|p| Action::from_str(p).ok())
+            .collect()
+    }
+
+    fn parse_relation(relation: &str) -> HealthcareRelation {
+        HealthcareRelation::from_str(relation)
+            .unwrap_or_else(|_| HealthcareRelation::Custom(relation.to_string()))
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        if status == StatusCode::FORBIDDEN {
+            tracing::warn!("{}: {}", context, e);
+        } else {
+            tracing::error!("{}: {}", context, e);
+        }
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+
+    fn role_to_response(role: Role) -> RoleResponse {
+        RoleResponse {
+            id: role.id,
+            name: role.name,
+            description: role.description,
+            permissions: role.permissions.iter().map(|a| a.to_string()).collect(),
+            is_system: role.is_system,
+            created_at: role.created_at,
+            updated_at: role.updated_at,
+        }
+    }
+
+    fn tuple_to_response(tuple: RelationshipTuple) -> RoleGrantResponse {
+        RoleGrantResponse {
+            resource: tuple.object.to_string(),
+            relation: tuple.relation.to_string(),
+            subject: tuple.subject.to_string(),
+            expires_at: tuple.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
@@ -0,0 +1,480 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::{
+    Action, AuthorizationStorage, HealthcareRelation, PostgresAuthorizationStorage, RelationshipTuple,
+    Resource, Subject,
+};
+
+// Import SQL queries from separate file
+use crate::modules::role::role_sql::*;
+
+/// Role with its permission bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Role {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Action>,
+    pub is_system: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Role {
+    /// Subject used when this role appears in relationship tuples
+    pub fn subject(&self) -> Subject {
+        Subject::Role(self.id.to_string())
+    }
+}
+
+/// User holding a role
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleMember {
+    pub role_id: Uuid,
+    pub user_id: Uuid,
+    pub assigned_by: Option<Uuid>,
+    pub assigned_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Service for role and permission administration
+pub struct RoleService {
+    pool: PgPool,
+    storage: PostgresAuthorizationStorage,
+}
+
+impl RoleService {
+    pub fn new(pool: PgPool) -> Self {
+        let storage = PostgresAuthorizationStorage::new(pool.clone());
+        Self { pool, storage }
+    }
+
+    /// Create a new role. The actor must hold every permission in the bundle.
+    pub async fn create_role(
+        &self,
+        actor: Uuid,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<Action>,
+    ) -> Result<Role, HimsError> {
+        Self::validate_name(name)?;
+        let held = self.require_action(actor, Action::ManageRoles).await?;
+        Self::check_escalation(&held, &permissions)?;
+
+        if self.get_role_by_name(name).await?.is_some() {
+            return Err(HimsError::ValidationError {
+                message: format!("Role '{}' already exists", name),
+            });
+        }
+
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+
+        sqlx::query(INSERT_ROLE)
+            .bind(id)
+            .bind(name)
+            .bind(description.as_ref())
+            .bind(Self::permissions_to_json(&permissions))
+            .bind(actor)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Role {} created by {}", name, actor);
+
+        Ok(Role {
+            id,
+            name: name.to_string(),
+            description,
+            permissions,
+            is_system: false,
+            created_by: Some(actor),
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Get role by ID
+    pub async fn get_role(&self, id: Uuid) -> Result<Option<Role>, HimsError> {
+        let row = sqlx::query(GET_ROLE_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.map(|row| Self::row_to_role(&row)))
+    }
+
+    /// Get role by name
+    pub async fn get_role_by_name(&self, name: &str) -> Result<Option<Role>, HimsError> {
+        let row = sqlx::query(GET_ROLE_BY_NAME)
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.map(|row| Self::row_to_role(&row)))
+    }
+
+    /// List active roles
+    pub async fn list_roles(&self, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Role>, HimsError> {
+        let rows = sqlx::query(LIST_ROLES)
+            .bind(limit.unwrap_or(50))
+            .bind(offset.unwrap_or(0))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_role).collect())
+    }
+
+    /// Update a role's name, description and permission bundle
+    pub async fn update_role(
+        &self,
+        actor: Uuid,
+        id: Uuid,
+        name: &str,
+        description: Option<String>,
+        permissions: Vec<Action>,
+    ) -> Result<Role, HimsError> {
+        Self::validate_name(name)?;
+        let held = self.require_action(actor, Action::ManageRoles).await?;
+        let existing = self.get_mutable_role(id).await?;
+
+        // Widening a role that the actor may not fully hold is still escalation,
+        // so both the old and the new bundle are checked.
+        Self::check_escalation(&held, &existing.permissions)?;
+        Self::check_escalation(&held, &permissions)?;
+
+        let now = Utc::now();
+        sqlx::query(UPDATE_ROLE)
+            .bind(id)
+            .bind(name)
+            .bind(description.as_ref())
+            .bind(Self::permissions_to_json(&permissions))
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Role {} updated by {}", id, actor);
+
+        Ok(Role {
+            name: name.to_string(),
+            description,
+            permissions,
+            updated_at: now,
+            ..existing
+        })
+    }
+
+    /// Soft delete a role and revoke all of its memberships
+    pub async fn delete_role(&self, actor: Uuid, id: Uuid) -> Result<(), HimsError> {
+        let held = self.require_action(actor, Action::ManageRoles).await?;
+        let existing = self.get_mutable_role(id).await?;
+        Self::check_escalation(&held, &existing.permissions)?;
+
+        let mut tx = self.pool.begin().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(DELETE_ROLE)
+            .bind(id)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(DEACTIVATE_ROLE_MEMBERS)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tx.commit().await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Role {} deleted by {}", id, actor);
+        Ok(())
+    }
+
+    /// Add a user to a role
+    pub async fn add_member(
+        &self,
+        actor: Uuid,
+        role_id: Uuid,
+        user_id: Uuid,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<RoleMember, HimsError> {
+        if actor == user_id {
+            return Err(HimsError::SecurityError {
+                message: "Users cannot change their own role memberships".to_string(),
+            });
+        }
+
+        let held = self.require_action(actor, Action::ManageRoles).await?;
+        let role = self.get_role(role_id).await?.ok_or_else(|| Self::role_not_found(role_id))?;
+        Self::check_escalation(&held, &role.permissions)?;
+
+        let now = Utc::now();
+        sqlx::query(INSERT_ROLE_MEMBER)
+            .bind(Uuid::new_v4())
+            .bind(role_id)
+            .bind(user_id)
+            .bind(actor)
+            .bind(now)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("User {} assigned role {} by {}", user_id, role.name, actor);
+
+        Ok(RoleMember {
+            role_id,
+            user_id,
+            assigned_by: Some(actor),
+            assigned_at: now,
+            expires_at,
+        })
+    }
+
+    /// Remove a user from a role
+    pub async fn remove_member(&self, actor: Uuid, role_id: Uuid, user_id: Uuid) -> Result<(), HimsError> {
+        if actor == user_id {
+            return Err(HimsError::SecurityError {
+                message: "Users cannot change their own role memberships".to_string(),
+            });
+        }
+
+        let held = self.require_action(actor, Action::ManageRoles).await?;
+        let role = self.get_role(role_id).await?.ok_or_else(|| Self::role_not_found(role_id))?;
+        Self::check_escalation(&held, &role.permissions)?;
+
+        sqlx::query(REMOVE_ROLE_MEMBER)
+            .bind(role_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("User {} removed from role {} by {}", user_id, role.name, actor);
+        Ok(())
+    }
+
+    /// List users holding a role
+    pub async fn get_members(&self, role_id: Uuid) -> Result<Vec<RoleMember>, HimsError> {
+        let rows = sqlx::query(GET_ROLE_MEMBERS)
+            .bind(role_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.into_iter().map(|row| RoleMember {
+            role_id: row.get("role_id"),
+            user_id: row.get("user_id"),
+            assigned_by: row.get("assigned_by"),
+            assigned_at: row.get("assigned_at"),
+            expires_at: row.get("expires_at"),
+        }).collect())
+    }
+
+    /// Grant a role a relationship on a resource by writing a `Subject::Role` tuple
+    pub async fn grant_relationship(
+        &self,
+        actor: Uuid,
+        role_id: Uuid,
+        resource: Resource,
+        relation: HealthcareRelation,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<RelationshipTuple, HimsError> {
+        let held = self.require_action(actor, Action::ManagePermissions).await?;
+        let role = self.get_role(role_id).await?.ok_or_else(|| Self::role_not_found(role_id))?;
+        Self::check_escalation(&held, &role.permissions)?;
+        Self::check_escalation(&held, &relation.default_permissions())?;
+
+        let tuple = RelationshipTuple {
+            object: resource,
+            relation,
+            subject: role.subject(),
+            context: None,
+            expires_at,
+            created_by: Some(actor),
+            created_at: Utc::now(),
+            metadata: Default::default(),
+        };
+
+        self.storage.store_relationship(&tuple).await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Role {} granted {} on {} by {}", role.name, tuple.relation, tuple.object, actor);
+        Ok(tuple)
+    }
+
+    /// Revoke a relationship previously granted to a role
+    pub async fn revoke_relationship(
+        &self,
+        actor: Uuid,
+        role_id: Uuid,
+        resource: Resource,
+        relation: HealthcareRelation,
+    ) -> Result<(), HimsError> {
+        let held = self.require_action(actor, Action::ManagePermissions).await?;
+        let role = self.get_role(role_id).await?.ok_or_else(|| Self::role_not_found(role_id))?;
+        Self::check_escalation(&held, &relation.default_permissions())?;
+
+        let tuple = RelationshipTuple {
+            object: resource,
+            relation,
+            subject: role.subject(),
+            context: None,
+            expires_at: None,
+            created_by: Some(actor),
+            created_at: Utc::now(),
+            metadata: Default::default(),
+        };
+
+        self.storage.remove_relationship(&tuple).await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Role {} revoked {} on {} by {}", role.name, tuple.relation, tuple.object, actor);
+        Ok(())
+    }
+
+    /// Get the union of permissions a user holds through their roles
+    pub async fn get_user_permissions(&self, user_id: Uuid) -> Result<HashSet<Action>, HimsError> {
+        let rows = sqlx::query(GET_USER_PERMISSIONS)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Action::from_str(&row.get::<String, _>("permission")).ok())
+            .collect())
+    }
+
+    /// Get the names of the roles a user currently holds
+    pub async fn get_user_role_names(&self, user_id: Uuid) -> Result<Vec<String>, HimsError> {
+        let rows = sqlx::query(GET_USER_ROLE_NAMES)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(|row| row.get("name")).collect())
+    }
+
+    /// Reject any bundle containing a permission the actor does not hold
+    pub fn check_escalation(held: &HashSet<Action>, requested: &[Action]) -> Result<(), HimsError> {
+        let missing: Vec<String> = requested
+            .iter()
+            .filter(|action| !held.contains(action))
+            .map(|action| action.to_string())
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(HimsError::SecurityError {
+                message: format!("Privilege escalation denied, actor lacks: {}", missing.join(", ")),
+            })
+        }
+    }
+
+    /// Load the actor's permissions and require a specific administrative action
+    async fn require_action(&self, actor: Uuid, action: Action) -> Result<HashSet<Action>, HimsError> {
+        let held = self.get_user_permissions(actor).await?;
+        if !held.contains(&action) {
+            tracing::warn!("User {} lacks {} permission", actor, action);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", action),
+            });
+        }
+        Ok(held)
+    }
+
+    /// Load a role that may be changed through the API
+    async fn get_mutable_role(&self, id: Uuid) -> Result<Role, HimsError> {
+        let role = self.get_role(id).await?.ok_or_else(|| Self::role_not_found(id))?;
+        if role.is_system {
+            return Err(HimsError::SecurityError {
+                message: format!("System role '{}' cannot be modified", role.name),
+            });
+        }
+        Ok(role)
+    }
+
+    fn validate_name(name: &str) -> Result<(), HimsError> {
+        let valid = !name.is_empty()
+            && name.len() <= 100
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid {
+            Ok(())
+        } else {
+            Err(HimsError::ValidationError {
+                message: "Role name must be 1-100 characters of letters, digits, '_' or '-'".to_string(),
+            })
+        }
+    }
+
+    fn role_not_found(id: Uuid) -> HimsError {
+        HimsError::ValidationError {
+            message: format!("Role with id {} not found", id),
+        }
+    }
+
+    fn permissions_to_json(permissions: &[Action]) -> serde_json::Value {
+        serde_json::Value::Array(
+            permissions.iter().map(|a| serde_json::Value::String(a.to_string())).collect(),
+        )
+    }
+
+    fn row_to_role(row: &sqlx::postgres::PgRow) -> Role {
+        let permissions: serde_json::Value = row.get("permissions");
+        let permissions = permissions
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .filter_map(|s| Action::from_str(s).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Role {
+            id: row.get("id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            permissions,
+            is_system: row.get("is_system"),
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalation_requires_every_permission() {
+        let held: HashSet<Action> = [Action::Read, Action::ManageRoles].into_iter().collect();
+
+        assert!(RoleService::check_escalation(&held, &[Action::Read]).is_ok());
+        assert!(RoleService::check_escalation(&held, &[]).is_ok());
+        assert!(RoleService::check_escalation(&held, &[Action::Read, Action::Prescribe]).is_err());
+        assert!(RoleService::check_escalation(&held, &[Action::Custom("export".to_string())]).is_err());
+    }
+}
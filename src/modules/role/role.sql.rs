@@ -0,0 +1,109 @@
+//! Role SQL Queries
+//! 
+//! This file contains all SQL queries used by the role service
+//! for clean separation of concerns and better maintainability.
+
+/// Insert a new role
+pub const INSERT_ROLE: &str = r#"
+    INSERT INTO roles (
+        id, name, description, permissions, is_system, is_active, created_by, created_at, updated_at
+    ) VALUES (
+        $1, $2, $3, $4, false, true, $5, $6, $6
+    )
+"#;
+
+/// Get role by ID
+pub const GET_ROLE_BY_ID: &str = r#"
+    SELECT id, name, description, permissions, is_system, is_active, created_by, created_at, updated_at
+    FROM roles
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Get role by name
+pub const GET_ROLE_BY_NAME: &str = r#"
+    SELECT id, name, description, permissions, is_system, is_active, created_by, created_at, updated_at
+    FROM roles
+    WHERE name = $1 AND is_active = true
+"#;
+
+/// List active roles
+pub const LIST_ROLES: &str = r#"
+    SELECT id, name, description, permissions, is_system, is_active, created_by, created_at, updated_at
+    FROM roles
+    WHERE is_active = true
+    ORDER BY name
+    LIMIT $1 OFFSET $2
+"#;
+
+/// Update a non-system role
+pub const UPDATE_ROLE: &str = r#"
+    UPDATE roles
+    SET name = $2, description = $3, permissions = $4, updated_at = $5
+    WHERE id = $1 AND is_active = true AND is_system = false
+"#;
+
+/// Soft delete a non-system role
+pub const DELETE_ROLE: &str = r#"
+    UPDATE roles
+    SET is_active = false, updated_at = $2
+    WHERE id = $1 AND is_system = false
+"#;
+
+/// Deactivate all memberships of a role
+pub const DEACTIVATE_ROLE_MEMBERS: &str = r#"
+    UPDATE role_members
+    SET is_active = false
+    WHERE role_id = $1 AND is_active = true
+"#;
+
+/// Add a user to a role
+pub const INSERT_ROLE_MEMBER: &str = r#"
+    INSERT INTO role_members (
+        id, role_id, user_id, assigned_by, assigned_at, expires_at, is_active
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, true
+    )
+    ON CONFLICT (role_id, user_id) WHERE is_active = true
+    DO UPDATE SET assigned_by = EXCLUDED.assigned_by,
+                  assigned_at = EXCLUDED.assigned_at,
+                  expires_at = EXCLUDED.expires_at
+"#;
+
+/// Remove a user from a role
+pub const REMOVE_ROLE_MEMBER: &str = r#"
+    UPDATE role_members
+    SET is_active = false
+    WHERE role_id = $1 AND user_id = $2 AND is_active = true
+"#;
+
+/// List members of a role
+pub const GET_ROLE_MEMBERS: &str = r#"
+    SELECT role_id, user_id, assigned_by, assigned_at, expires_at
+    FROM role_members
+    WHERE role_id = $1 AND is_active = true
+      AND (expires_at IS NULL OR expires_at > NOW())
+    ORDER BY assigned_at
+"#;
+
+/// Get the union of permissions held by a user through active roles
+pub const GET_USER_PERMISSIONS: &str = r#"
+    SELECT DISTINCT jsonb_array_elements_text(r.permissions) AS permission
+    FROM role_members m
+    JOIN roles r ON r.id = m.role_id
+    WHERE m.user_id = $1
+      AND m.is_active = true
+      AND r.is_active = true
+      AND (m.expires_at IS NULL OR m.expires_at > NOW())
+"#;
+
+/// Get the names of the active roles a user is an active member of
+pub const GET_USER_ROLE_NAMES: &str = r#"
+    SELECT r.name
+    FROM role_members m
+    JOIN roles r ON r.id = m.role_id
+    WHERE m.user_id = $1
+      AND m.is_active = true
+      AND r.is_active = true
+      AND (m.expires_at IS NULL OR m.expires_at > NOW())
+    ORDER BY r.name
+"#;
@@ -0,0 +1,53 @@
+//! Scheduler Module
+//!
+//! This module provides the runner for background jobs including:
+//! - Cron-style schedules, changeable by platform admins
+//! - Leader election through a database lease, so that one server of a
+//!   multi-instance deployment runs scheduled jobs
+//! - A history of every run with what it did or why it failed
+//! - Alerts, logged and sent to a webhook, for jobs that keep failing
+//! - Manual runs of any job
+
+#[path = "scheduler.controller.rs"]
+pub mod scheduler_controller;
+#[path = "scheduler.cron.rs"]
+pub mod scheduler_cron;
+#[path = "scheduler.service.rs"]
+pub mod scheduler_service;
+#[path = "scheduler.sql.rs"]
+pub mod scheduler_sql;
+
+pub use scheduler_controller::SchedulerController;
+pub use scheduler_cron::CronSchedule;
+pub use scheduler_service::{JobRun, ScheduledJob, ScheduledJobInfo, SchedulerConfig, SchedulerService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Scheduler Module Configuration
+pub struct SchedulerModule {
+    pub service: Arc<SchedulerService>,
+    pub controller: Arc<SchedulerController>,
+}
+
+impl SchedulerModule {
+    /// Create a new Scheduler Module with dependency injection
+    pub fn new(db_pool: PgPool, config: SchedulerConfig) -> Self {
+        let service = Arc::new(SchedulerService::new(db_pool, config));
+        let controller = Arc::new(SchedulerController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<SchedulerService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,167 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::scheduler::scheduler_service::{JobRun, ScheduledJobInfo, SchedulerLeader, UpdateJobRequest};
+use crate::modules::scheduler::SchedulerService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+#[derive(Debug, Deserialize)]
+pub struct RunsQuery {
+    pub limit: Option<i64>,
+}
+
+/// Scheduler controller for platform admins managing background jobs
+pub struct SchedulerController {
+    scheduler_service: Arc<SchedulerService>,
+}
+
+impl SchedulerController {
+    /// Create new controller with injected service
+    pub fn new(scheduler_service: Arc<SchedulerService>) -> Self {
+        Self { scheduler_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/jobs", Self::list_jobs, "List background jobs with their latest runs (platform admin)")
+            .put("/jobs/:name", Self::update_job, "Change a job's schedule or turn it on or off (platform admin)")
+            .post("/jobs/:name/run", Self::run_job, "Run a job now (platform admin)")
+            .get("/jobs/:name/runs", Self::list_runs, "History of a job's runs (platform admin)")
+            .get("/leader", Self::leader, "Server running scheduled jobs (platform admin)")
+            .with_state(self.scheduler_service.clone())
+    }
+
+    /// List background jobs with their latest runs (platform admin)
+    pub async fn list_jobs(
+        State(service): State<Arc<SchedulerService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<ScheduledJobInfo>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_jobs(actor).await {
+            Ok(jobs) => Ok(Json(jobs)),
+            Err(e) => Err(Self::error_response("Failed to list jobs", e)),
+        }
+    }
+
+    /// Change a job's schedule or turn it on or off (platform admin)
+    pub async fn update_job(
+        State(service): State<Arc<SchedulerService>>,
+        headers: HeaderMap,
+        Path(name): Path<String>,
+        Json(payload): Json<UpdateJobRequest>,
+    ) -> Result<Json<ScheduledJobInfo>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.update_job(actor, &name, payload).await {
+            Ok(Some(job)) => Ok(Json(job)),
+            Ok(None) => Err(Self::not_found(&name)),
+            Err(e) => Err(Self::error_response("Failed to update job", e)),
+        }
+    }
+
+    /// Run a job now, answering as it starts (platform admin)
+    pub async fn run_job(
+        State(service): State<Arc<SchedulerService>>,
+        headers: HeaderMap,
+        Path(name): Path<String>,
+    ) -> Result<(StatusCode, Json<JobRun>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.run_now(actor, &name).await {
+            Ok(Some(run)) => Ok((StatusCode::ACCEPTED, Json(run))),
+            Ok(None) => Err(Self::not_found(&name)),
+            Err(e) => Err(Self::error_response("Failed to run job", e)),
+        }
+    }
+
+    /// History of a job's runs, newest first (platform admin)
+    pub async fn list_runs(
+        State(service): State<Arc<SchedulerService>>,
+        headers: HeaderMap,
+        Path(name): Path<String>,
+        Query(query): Query<RunsQuery>,
+    ) -> Result<Json<Vec<JobRun>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_runs(actor, &name, query.limit.unwrap_or(50)).await {
+            Ok(Some(runs)) => Ok(Json(runs)),
+            Ok(None) => Err(Self::not_found(&name)),
+            Err(e) => Err(Self::error_response("Failed to list job runs", e)),
+        }
+    }
+
+    /// Server running scheduled jobs (platform admin)
+    pub async fn leader(
+        State(service): State<Arc<SchedulerService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<SchedulerLeader>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.leader(actor).await {
+            Ok(leader) => Ok(Json(leader)),
+            Err(e) => Err(Self::error_response("Failed to get scheduler leader", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(name: &str) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Job not found".to_string(),
+                message: format!("No job named {}", name),
+            }),
+        )
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            HimsError::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
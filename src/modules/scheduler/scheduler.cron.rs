@@ -0,0 +1,245 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead to look for the next run before taking a schedule as one
+/// that never fires, such as `0 0 30 2 *`
+const SEARCH_DAYS: i64 = 5 * 366;
+
+/// A cron schedule in the five fields of crontab(5) — minute, hour, day of
+/// month, month and day of week — evaluated in UTC. Fields take `*`,
+/// numbers, ranges, `/` steps, comma lists and three-letter month and day
+/// names; `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` stand
+/// for their usual expansions. As in cron, when both the day of month and
+/// the day of week are restricted a day matching either runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = expression.trim();
+        let expanded = match expression.to_ascii_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@hourly" => "0 * * * *".to_string(),
+            other if other.starts_with('@') => return Err(format!("Unknown schedule '{}'", expression)),
+            _ => expression.to_string(),
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(format!(
+                "Schedule '{}' needs five fields: minute, hour, day of month, month and day of week",
+                expression
+            ));
+        };
+
+        // Sunday may be written as 7 as well as 0
+        let mut weekdays = parse_field(weekday, 0, 7, &DAY_NAMES, "day of week")?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse_field(minute, 0, 59, &[], "minute")?,
+            hours: parse_field(hour, 0, 23, &[], "hour")?,
+            days: parse_field(day, 1, 31, &[], "day of month")?,
+            months: parse_field(month, 1, 12, &MONTH_NAMES, "month")?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    /// The schedule as written
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// First time the schedule fires strictly after `after`, or `None` if it
+    /// does not fire within the next few years
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.naive_utc().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start.date() + Duration::days(SEARCH_DAYS);
+
+        let mut date = start.date();
+        let mut first_day = true;
+        while date <= limit {
+            if !has(self.months, date.month()) {
+                date = first_of_next_month(date)?;
+                first_day = false;
+                continue;
+            }
+            if self.runs_on(date) {
+                let from = if first_day { start.time() } else { NaiveTime::MIN };
+                if let Some(time) = self.first_time_from(from) {
+                    return Some(NaiveDateTime::new(date, time).and_utc());
+                }
+            }
+            date = date.succ_opt()?;
+            first_day = false;
+        }
+        None
+    }
+
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// Earliest matching minute of a day at or after `from`
+    fn first_time_from(&self, from: NaiveTime) -> Option<NaiveTime> {
+        (from.hour()..24).filter(|hour| has(self.hours, *hour)).find_map(|hour| {
+            let first_minute = if hour == from.hour() { from.minute() } else { 0 };
+            (first_minute..60)
+                .find(|minute| has(self.minutes, *minute))
+                .and_then(|minute| NaiveTime::from_hms_opt(hour, minute, 0))
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::parse(expression)
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = String;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        Self::parse(&expression)
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.expression
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    match date.month() {
+        12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+    }
+}
+
+/// Values of one field as a bit set
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], label: &str) -> Result<u64, String> {
+    let mut set = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("Invalid step '{}' in {} field '{}'", step, label, field))?;
+                (range, step)
+            }
+            None => (item, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start, min, max, names, label)?, value(end, min, max, names, label)?)
+        } else {
+            let start = value(range, min, max, names, label)?;
+            // `5/15` runs from 5 to the end of the range
+            (start, if item.contains('/') { max } else { start })
+        };
+        if start > end {
+            return Err(format!("Range '{}' in {} field runs backwards", range, label));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn value(text: &str, min: u32, max: u32, names: &[&str], label: &str) -> Result<u32, String> {
+    let lowered = text.to_ascii_lowercase();
+    let value = match names.iter().position(|name| *name == lowered) {
+        Some(index) => index as u32 + min,
+        None => text.parse().map_err(|_| format!("Invalid {} '{}'", label, text))?,
+    };
+    if value < min || value > max {
+        return Err(format!("{} {} is outside {}-{}", label, value, min, max));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap())
+    }
+
+    fn next(expression: &str, after: &str) -> Option<DateTime<Utc>> {
+        CronSchedule::parse(expression).unwrap().next_after(at(after))
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(next("*/15 * * * *", "2024-03-01 10:07"), Some(at("2024-03-01 10:15")));
+        assert_eq!(next("*/15 * * * *", "2024-03-01 10:15"), Some(at("2024-03-01 10:30")));
+        assert_eq!(next("0 3 * * *", "2024-03-01 03:00"), Some(at("2024-03-02 03:00")));
+        assert_eq!(next("30 2 1 * *", "2024-12-05 00:00"), Some(at("2025-01-01 02:30")));
+        assert_eq!(next("0 9-17/4 * * mon-fri", "2024-03-01 14:00"), Some(at("2024-03-01 17:00")));
+        assert_eq!(next("0 0 29 feb *", "2024-03-01 00:00"), Some(at("2028-02-29 00:00")));
+        assert_eq!(next("0 9-17/4 * * mon-fri", "2024-03-01 17:00"), Some(at("2024-03-04 09:00")));
+        assert_eq!(next("@weekly", "2024-03-01 00:00"), Some(at("2024-03-03 00:00")));
+        assert_eq!(next("0 0 * * 7", "2024-03-01 00:00"), Some(at("2024-03-03 00:00")));
+        // Either the 15th or a Monday
+        assert_eq!(next("0 0 15 * 1", "2024-03-05 00:00"), Some(at("2024-03-11 00:00")));
+        assert_eq!(next("0 0 30 2 *", "2024-01-01 00:00"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 5-2 * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * foo *").is_err());
+        assert!(CronSchedule::parse("@fortnightly").is_err());
+        assert_eq!(CronSchedule::parse(" 0 0 * * SUN ").unwrap().to_string(), "0 0 * * SUN");
+    }
+}
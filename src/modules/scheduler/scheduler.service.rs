@@ -0,0 +1,671 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{current_tenant_id, with_tenant, TenantContext, DEFAULT_TENANT_ID};
+use crate::modules::authorization::Action;
+use crate::modules::role::RoleService;
+use crate::modules::scheduler::scheduler_cron::CronSchedule;
+
+// Import SQL queries from separate file
+use crate::modules::scheduler::scheduler_sql::*;
+
+/// Lease held by the server that runs scheduled jobs
+const LEADER_LEASE: &str = "scheduler";
+const ALERT_TIMEOUT: Duration = Duration::from_secs(10);
+const HISTORY_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A background job the scheduler runs
+#[async_trait]
+pub trait ScheduledJob: Send + Sync {
+    /// Do one run, returning what it did for the job's history. Called
+    /// under a system tenant.
+    async fn run(&self) -> Result<Value, HimsError>;
+}
+
+/// Scheduler settings
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// How often to look for due jobs and renew the leader's lease
+    pub tick: Duration,
+    /// How long a leader's lease lasts without renewal, after which another
+    /// server takes over
+    pub lease: Duration,
+    /// Consecutive failures of a job at which it is alerted
+    pub alert_after: i32,
+    /// Where failure alerts are POSTed as JSON, if anywhere
+    pub alert_webhook_url: Option<String>,
+    /// Days of job history kept
+    pub history_days: i32,
+    /// Runs still running after this long are taken as abandoned
+    pub abandon_after: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            tick: Duration::from_secs(15),
+            lease: Duration::from_secs(60),
+            alert_after: 1,
+            alert_webhook_url: None,
+            history_days: 30,
+            abandon_after: Duration::from_secs(24 * 3600),
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Settings from `SCHEDULER_TICK_SECONDS`, `SCHEDULER_LEASE_SECONDS`,
+    /// `SCHEDULER_ALERT_AFTER`, `SCHEDULER_ALERT_WEBHOOK_URL` and
+    /// `SCHEDULER_HISTORY_DAYS`
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|value| value.trim().parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            tick: var("SCHEDULER_TICK_SECONDS").map(Duration::from_secs).unwrap_or(defaults.tick),
+            lease: var("SCHEDULER_LEASE_SECONDS").map(Duration::from_secs).unwrap_or(defaults.lease),
+            alert_after: var("SCHEDULER_ALERT_AFTER").filter(|count| *count > 0).unwrap_or(defaults.alert_after),
+            alert_webhook_url: std::env::var("SCHEDULER_ALERT_WEBHOOK_URL").ok().filter(|url| !url.trim().is_empty()),
+            history_days: var("SCHEDULER_HISTORY_DAYS").unwrap_or(defaults.history_days),
+            abandon_after: defaults.abandon_after,
+        }
+    }
+}
+
+/// A job as known to the scheduler, with its latest run
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledJobInfo {
+    pub name: String,
+    pub description: String,
+    pub schedule: String,
+    pub default_schedule: String,
+    pub enabled: bool,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub consecutive_failures: i32,
+    /// Whether this server has the job; jobs of features since removed stay
+    /// listed with their history
+    pub registered: bool,
+    pub last_run: Option<JobRunSummary>,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRunSummary {
+    pub id: Uuid,
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+/// One run of a job
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub id: Uuid,
+    pub job_name: String,
+    /// `schedule` or `manual`
+    pub trigger: String,
+    /// `running`, `succeeded` or `failed`
+    pub status: String,
+    /// Server that ran it
+    pub instance_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub output: Option<Value>,
+    pub error: Option<String>,
+    pub triggered_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateJobRequest {
+    /// New cron schedule; the job's default when empty
+    pub schedule: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+/// Which server leads the scheduler
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerLeader {
+    pub holder: Option<String>,
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// This server
+    pub instance_id: String,
+    pub is_leader: bool,
+}
+
+struct RegisteredJob {
+    description: String,
+    schedule: CronSchedule,
+    job: Arc<dyn ScheduledJob>,
+}
+
+/// Runs registered background jobs on cron schedules. Every server
+/// registers the same jobs, but only the one holding the leader lease runs
+/// them on schedule; each run is recorded, and jobs that keep failing are
+/// alerted.
+pub struct SchedulerService {
+    pool: PgPool,
+    role_service: RoleService,
+    config: SchedulerConfig,
+    instance_id: String,
+    jobs: RwLock<HashMap<String, RegisteredJob>>,
+    running: Mutex<HashSet<String>>,
+    http_client: reqwest::Client,
+}
+
+impl SchedulerService {
+    pub fn new(pool: PgPool, config: SchedulerConfig) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+            config,
+            instance_id: Self::new_instance_id(),
+            jobs: RwLock::new(HashMap::new()),
+            running: Mutex::new(HashSet::new()),
+            http_client: reqwest::Client::builder()
+                .timeout(ALERT_TIMEOUT)
+                .user_agent("open-hims-scheduler")
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// This server's name in leases and job history: `HIMS_INSTANCE_ID`, or
+    /// the host name and process ID
+    fn new_instance_id() -> String {
+        std::env::var("HIMS_INSTANCE_ID").ok().filter(|id| !id.trim().is_empty()).unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "hims".to_string());
+            format!("{}-{}-{}", host, std::process::id(), &Uuid::new_v4().simple().to_string()[..8])
+        })
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// Run `job` as `name` on `schedule` unless an admin has changed it.
+    /// Register jobs before the scheduler is spawned.
+    pub fn register(
+        &self,
+        name: &str,
+        description: &str,
+        schedule: &str,
+        job: Arc<dyn ScheduledJob>,
+    ) -> Result<(), HimsError> {
+        let schedule = CronSchedule::parse(schedule).map_err(|message| HimsError::ConfigurationError {
+            message: format!("Job {}: {}", name, message),
+        })?;
+        let job = RegisteredJob {
+            description: description.to_string(),
+            schedule,
+            job,
+        };
+        self.jobs
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(name.to_string(), job);
+        Ok(())
+    }
+
+    /// Record registered jobs, starting their schedules
+    async fn sync_jobs(&self) -> Result<(), HimsError> {
+        let jobs: Vec<(String, String, CronSchedule)> = self
+            .jobs
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|(name, job)| (name.clone(), job.description.clone(), job.schedule.clone()))
+            .collect();
+
+        for (name, description, schedule) in jobs {
+            sqlx::query(UPSERT_JOB)
+                .bind(&name)
+                .bind(&description)
+                .bind(schedule.expression())
+                .bind(schedule.next_after(Utc::now()))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Take or renew the leader lease. Returns whether this server leads.
+    async fn try_lead(&self) -> Result<bool, HimsError> {
+        let holder: Option<String> = sqlx::query_scalar(ACQUIRE_LEASE)
+            .bind(LEADER_LEASE)
+            .bind(&self.instance_id)
+            .bind(self.config.lease.as_secs_f64())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(holder.is_some())
+    }
+
+    /// Start the jobs that are due, each moved to its next time first so
+    /// that a failing job is not retried until then. Returns how many
+    /// started. Only the leader calls this.
+    async fn run_due(self: &Arc<Self>) -> Result<usize, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let due = sqlx::query(CLAIM_DUE_JOBS)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut started = Vec::new();
+        for row in due {
+            let name: String = row.get("name");
+            let expression: String = row.get("schedule");
+            let next_run_at = match CronSchedule::parse(&expression) {
+                Ok(schedule) => schedule.next_after(Utc::now()),
+                Err(e) => {
+                    tracing::error!("Job {} has an invalid schedule '{}': {}", name, expression, e);
+                    None
+                }
+            };
+            sqlx::query(SET_NEXT_RUN)
+                .bind(&name)
+                .bind(next_run_at)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+            started.push(name);
+        }
+        tx.commit().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut count = 0;
+        for name in started {
+            match self.start(&name, "schedule", None).await {
+                Ok(Some(_)) => count += 1,
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to start job {}: {}", name, e),
+            }
+        }
+        Ok(count)
+    }
+
+    /// Start a run of job `name` in the background, returning it as it
+    /// starts, or `None` if this server does not have the job or is already
+    /// running it
+    async fn start(
+        self: &Arc<Self>,
+        name: &str,
+        trigger: &str,
+        triggered_by: Option<Uuid>,
+    ) -> Result<Option<JobRun>, HimsError> {
+        let Some(job) = self.job(name) else {
+            tracing::debug!("Job {} is due but not registered on this server", name);
+            return Ok(None);
+        };
+        if !self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(name.to_string()) {
+            tracing::warn!("Job {} is still running, skipping this run", name);
+            return Ok(None);
+        }
+
+        let row = sqlx::query(INSERT_RUN)
+            .bind(Uuid::new_v4())
+            .bind(name)
+            .bind(trigger)
+            .bind(&self.instance_id)
+            .bind(triggered_by)
+            .fetch_one(&self.pool)
+            .await;
+        let run = match row {
+            Ok(row) => Self::row_to_run(&row),
+            Err(e) => {
+                self.finished(name);
+                return Err(HimsError::DatabaseError(e.to_string()));
+            }
+        };
+
+        let scheduler = self.clone();
+        let started = run.clone();
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            // A panicking job fails its run rather than the scheduler
+            let execution = tokio::spawn(with_tenant(TenantContext::system(), async move { job.run().await }));
+            let outcome = match execution.await {
+                Ok(outcome) => outcome,
+                Err(e) => Err(HimsError::InternalError {
+                    message: format!("Job panicked: {}", e),
+                }),
+            };
+            if let Err(e) = scheduler.finish(&started, outcome).await {
+                tracing::error!("Failed to record run {} of job {}: {}", started.id, started.job_name, e);
+            }
+            scheduler.finished(&started.job_name);
+        }));
+        Ok(Some(run))
+    }
+
+    fn job(&self, name: &str) -> Option<Arc<dyn ScheduledJob>> {
+        self.jobs
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(name)
+            .map(|registered| registered.job.clone())
+    }
+
+    fn finished(&self, name: &str) {
+        self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(name);
+    }
+
+    /// Record how a run went, alerting when the job has now failed
+    /// `alert_after` times in a row and when it recovers after an alert
+    async fn finish(&self, run: &JobRun, outcome: Result<Value, HimsError>) -> Result<(), HimsError> {
+        let (status, output, error) = match &outcome {
+            Ok(output) => ("succeeded", Some(output.clone()), None),
+            Err(e) => ("failed", None, Some(e.to_string())),
+        };
+        sqlx::query(FINISH_RUN)
+            .bind(run.id)
+            .bind(status)
+            .bind(&output)
+            .bind(&error)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let row = sqlx::query(RECORD_OUTCOME)
+            .bind(&run.job_name)
+            .bind(outcome.is_ok())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let previous: i32 = row.get("previous");
+        let current: i32 = row.get("current");
+
+        match error {
+            None => {
+                let output = output.unwrap_or_default();
+                tracing::info!("Job {} succeeded: {}", run.job_name, output);
+                if previous >= self.config.alert_after {
+                    tracing::info!("Job {} recovered after {} failures", run.job_name, previous);
+                    self.alert(run, "recovered", None, previous).await;
+                }
+            }
+            Some(error) => {
+                tracing::error!("Job {} failed ({} in a row): {}", run.job_name, current, error);
+                if current == self.config.alert_after {
+                    self.alert(run, "failing", Some(&error), current).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// POST an alert about a job to the configured webhook
+    async fn alert(&self, run: &JobRun, state: &str, error: Option<&str>, failures: i32) {
+        let Some(url) = &self.config.alert_webhook_url else {
+            return;
+        };
+        let text = match error {
+            Some(error) => format!("Job {} has failed {} times in a row: {}", run.job_name, failures, error),
+            None => format!("Job {} succeeded again after {} failures", run.job_name, failures),
+        };
+        let body = json!({
+            "text": text,
+            "job": run.job_name,
+            "state": state,
+            "run_id": run.id,
+            "consecutive_failures": failures,
+            "error": error,
+            "instance_id": self.instance_id,
+        });
+
+        match self.http_client.post(url).json(&body).send().await {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => tracing::error!("Job alert webhook answered {}", response.status()),
+            Err(e) => tracing::error!("Failed to send job alert: {}", e),
+        }
+    }
+
+    /// Fail runs left behind by stopped servers and forget old history
+    async fn clean_history(&self) -> Result<(), HimsError> {
+        let abandoned = sqlx::query(ABANDON_STALE_RUNS)
+            .bind(self.config.abandon_after.as_secs_f64())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if abandoned > 0 {
+            tracing::warn!("Marked {} abandoned job runs as failed", abandoned);
+        }
+        sqlx::query(PURGE_RUNS)
+            .bind(self.config.history_days)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Jobs with their schedules and latest runs, for platform admins
+    pub async fn list_jobs(&self, actor: Uuid) -> Result<Vec<ScheduledJobInfo>, HimsError> {
+        self.require_platform_admin(actor).await?;
+        self.find_jobs(None).await
+    }
+
+    /// Change a job's schedule or turn it on or off, for platform admins
+    pub async fn update_job(
+        &self,
+        actor: Uuid,
+        name: &str,
+        request: UpdateJobRequest,
+    ) -> Result<Option<ScheduledJobInfo>, HimsError> {
+        self.require_platform_admin(actor).await?;
+        let Some(job) = self.find_job(name).await? else {
+            return Ok(None);
+        };
+
+        let schedule = match request.schedule.as_deref().map(str::trim) {
+            Some("") => job.default_schedule.clone(),
+            Some(schedule) => schedule.to_string(),
+            None => job.schedule.clone(),
+        };
+        let parsed = CronSchedule::parse(&schedule).map_err(|message| HimsError::ValidationError { message })?;
+        let enabled = request.enabled.unwrap_or(job.enabled);
+
+        sqlx::query(UPDATE_JOB)
+            .bind(name)
+            .bind(parsed.expression())
+            .bind(enabled)
+            .bind(parsed.next_after(Utc::now()))
+            .bind(actor)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Job {} set to '{}', enabled {}, by {}", name, parsed, enabled, actor);
+        self.find_job(name).await
+    }
+
+    /// Run a job now on this server, whatever its schedule, for platform
+    /// admins. Returns the run as it starts.
+    pub async fn run_now(self: &Arc<Self>, actor: Uuid, name: &str) -> Result<Option<JobRun>, HimsError> {
+        self.require_platform_admin(actor).await?;
+        if self.find_job(name).await?.is_none() {
+            return Ok(None);
+        }
+
+        tracing::info!("Job {} started by {}", name, actor);
+        match self.start(name, "manual", Some(actor)).await? {
+            Some(run) => Ok(Some(run)),
+            None if self.job(name).is_none() => Err(HimsError::PreconditionFailed {
+                message: format!("Job {} is not available on this server", name),
+            }),
+            None => Err(HimsError::ConflictError {
+                message: format!("Job {} is already running", name),
+            }),
+        }
+    }
+
+    /// A job's latest runs, newest first, for platform admins
+    pub async fn list_runs(&self, actor: Uuid, name: &str, limit: i64) -> Result<Option<Vec<JobRun>>, HimsError> {
+        self.require_platform_admin(actor).await?;
+        if self.find_job(name).await?.is_none() {
+            return Ok(None);
+        }
+
+        let rows = sqlx::query(LIST_RUNS)
+            .bind(name)
+            .bind(limit.clamp(1, 500))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(Some(rows.iter().map(Self::row_to_run).collect()))
+    }
+
+    /// Which server leads, for platform admins
+    pub async fn leader(&self, actor: Uuid) -> Result<SchedulerLeader, HimsError> {
+        self.require_platform_admin(actor).await?;
+
+        let row = sqlx::query(GET_LEASE)
+            .bind(LEADER_LEASE)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let holder: Option<String> = row.as_ref().map(|row| row.get("holder"));
+        let expires_at: Option<DateTime<Utc>> = row.as_ref().map(|row| row.get("expires_at"));
+
+        Ok(SchedulerLeader {
+            is_leader: holder.as_deref() == Some(self.instance_id.as_str())
+                && expires_at.is_some_and(|expires_at| expires_at > Utc::now()),
+            holder,
+            acquired_at: row.as_ref().map(|row| row.get("acquired_at")),
+            expires_at,
+            instance_id: self.instance_id.clone(),
+        })
+    }
+
+    async fn find_job(&self, name: &str) -> Result<Option<ScheduledJobInfo>, HimsError> {
+        Ok(self.find_jobs(Some(name)).await?.pop())
+    }
+
+    async fn find_jobs(&self, name: Option<&str>) -> Result<Vec<ScheduledJobInfo>, HimsError> {
+        let rows = sqlx::query(LIST_JOBS)
+            .bind(name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let registered = self.jobs.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let name: String = row.get("name");
+                let last_run_id: Option<Uuid> = row.get("last_run_id");
+                ScheduledJobInfo {
+                    registered: registered.contains_key(&name),
+                    name,
+                    description: row.get("description"),
+                    schedule: row.get("schedule"),
+                    default_schedule: row.get("default_schedule"),
+                    enabled: row.get("enabled"),
+                    next_run_at: row.get("next_run_at"),
+                    consecutive_failures: row.get("consecutive_failures"),
+                    last_run: last_run_id.map(|id| JobRunSummary {
+                        id,
+                        status: row.get("last_run_status"),
+                        started_at: row.get("last_run_started_at"),
+                        finished_at: row.get("last_run_finished_at"),
+                        error: row.get("last_run_error"),
+                    }),
+                    updated_at: row.get("updated_at"),
+                    updated_by: row.get("updated_by"),
+                }
+            })
+            .collect())
+    }
+
+    fn row_to_run(row: &PgRow) -> JobRun {
+        JobRun {
+            id: row.get("id"),
+            job_name: row.get("job_name"),
+            trigger: row.get("trigger"),
+            status: row.get("status"),
+            instance_id: row.get("instance_id"),
+            started_at: row.get("started_at"),
+            finished_at: row.get("finished_at"),
+            output: row.get("output"),
+            error: row.get("error"),
+            triggered_by: row.get("triggered_by"),
+        }
+    }
+
+    /// Jobs run for every tenant, so only platform admins manage them
+    async fn require_platform_admin(&self, actor: Uuid) -> Result<(), HimsError> {
+        if current_tenant_id() != DEFAULT_TENANT_ID {
+            return Err(HimsError::SecurityError {
+                message: "Jobs can only be managed from the platform tenant".to_string(),
+            });
+        }
+        if !self.role_service.get_user_permissions(actor).await?.contains(&Action::Configure) {
+            tracing::warn!("User {} lacks {} permission", actor, Action::Configure);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", Action::Configure),
+            });
+        }
+        Ok(())
+    }
+
+    /// Record the registered jobs, then every `tick` of the configuration
+    /// contend for the leader lease and, while leading, run due jobs
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            if let Err(e) = self.sync_jobs().await {
+                tracing::error!("Failed to record scheduled jobs: {}", e);
+            }
+            tracing::info!("Scheduler started as {}", self.instance_id);
+
+            let mut ticker = tokio::time::interval(self.config.tick);
+            let mut leading = false;
+            let mut cleaned_at: Option<Instant> = None;
+            loop {
+                ticker.tick().await;
+                let now_leading = self.try_lead().await.unwrap_or_else(|e| {
+                    tracing::error!("Failed to renew scheduler lease: {}", e);
+                    false
+                });
+                if now_leading != leading {
+                    tracing::info!("Scheduler {} leadership: {}", self.instance_id, now_leading);
+                }
+                if now_leading && !leading {
+                    // Jobs a newer release registered reach the table once
+                    // one of its servers leads
+                    if let Err(e) = self.sync_jobs().await {
+                        tracing::error!("Failed to record scheduled jobs: {}", e);
+                    }
+                }
+                leading = now_leading;
+                if !leading {
+                    continue;
+                }
+
+                match self.run_due().await {
+                    Ok(0) => {}
+                    Ok(started) => tracing::debug!("Started {} scheduled jobs", started),
+                    Err(e) => tracing::error!("Failed to start due jobs: {}", e),
+                }
+                if cleaned_at.is_none_or(|at| at.elapsed() >= HISTORY_CLEANUP_INTERVAL) {
+                    if let Err(e) = self.clean_history().await {
+                        tracing::error!("Failed to clean job history: {}", e);
+                    }
+                    cleaned_at = Some(Instant::now());
+                }
+            }
+        }))
+    }
+}
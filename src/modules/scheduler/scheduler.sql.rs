@@ -0,0 +1,116 @@
+//! Scheduler SQL Queries
+//!
+//! This file contains all SQL queries used by the scheduler service.
+
+/// Take or renew lease $1 for holder $2 for $3 seconds, unless another
+/// holder's lease is still current. Returns a row only when $2 holds it.
+pub const ACQUIRE_LEASE: &str = r#"
+    INSERT INTO scheduler_leases (name, holder, acquired_at, expires_at)
+    VALUES ($1, $2, NOW(), NOW() + make_interval(secs => $3))
+    ON CONFLICT (name) DO UPDATE
+    SET holder = EXCLUDED.holder,
+        acquired_at = CASE
+            WHEN scheduler_leases.holder = EXCLUDED.holder THEN scheduler_leases.acquired_at
+            ELSE NOW()
+        END,
+        expires_at = EXCLUDED.expires_at
+    WHERE scheduler_leases.holder = EXCLUDED.holder OR scheduler_leases.expires_at < NOW()
+    RETURNING holder
+"#;
+
+pub const GET_LEASE: &str = r#"
+    SELECT name, holder, acquired_at, expires_at FROM scheduler_leases WHERE name = $1
+"#;
+
+/// Add a registered job, keeping the schedule and enabled flag of one
+/// already known so that admins' changes survive restarts
+pub const UPSERT_JOB: &str = r#"
+    INSERT INTO scheduled_jobs (name, description, schedule, default_schedule, next_run_at)
+    VALUES ($1, $2, $3, $3, $4)
+    ON CONFLICT (name) DO UPDATE
+    SET description = EXCLUDED.description,
+        default_schedule = EXCLUDED.default_schedule,
+        updated_at = NOW()
+"#;
+
+/// Jobs with their latest run, or only job $1 when given
+pub const LIST_JOBS: &str = r#"
+    SELECT j.name, j.description, j.schedule, j.default_schedule, j.enabled, j.next_run_at,
+           j.consecutive_failures, j.updated_at, j.updated_by,
+           r.id AS last_run_id, r.status AS last_run_status, r.started_at AS last_run_started_at,
+           r.finished_at AS last_run_finished_at, r.error AS last_run_error
+    FROM scheduled_jobs j
+    LEFT JOIN LATERAL (
+        SELECT id, status, started_at, finished_at, error
+        FROM scheduled_job_runs
+        WHERE job_name = j.name
+        ORDER BY started_at DESC
+        LIMIT 1
+    ) r ON true
+    WHERE $1::text IS NULL OR j.name = $1
+    ORDER BY j.name
+"#;
+
+/// Enabled jobs that are due, locked so that they run once
+pub const CLAIM_DUE_JOBS: &str = r#"
+    SELECT name, schedule
+    FROM scheduled_jobs
+    WHERE enabled = true AND next_run_at <= NOW()
+    ORDER BY next_run_at
+    FOR UPDATE SKIP LOCKED
+"#;
+
+pub const SET_NEXT_RUN: &str = r#"
+    UPDATE scheduled_jobs SET next_run_at = $2 WHERE name = $1
+"#;
+
+/// Change job $1's schedule and enabled flag, the next run following from
+/// the schedule
+pub const UPDATE_JOB: &str = r#"
+    UPDATE scheduled_jobs
+    SET schedule = $2, enabled = $3, next_run_at = $4, updated_at = NOW(), updated_by = $5
+    WHERE name = $1
+"#;
+
+pub const INSERT_RUN: &str = r#"
+    INSERT INTO scheduled_job_runs (id, job_name, trigger, status, instance_id, started_at, triggered_by)
+    VALUES ($1, $2, $3, 'running', $4, NOW(), $5)
+    RETURNING id, job_name, trigger, status, instance_id, started_at, finished_at, output, error, triggered_by
+"#;
+
+pub const FINISH_RUN: &str = r#"
+    UPDATE scheduled_job_runs
+    SET status = $2, output = $3, error = $4, finished_at = NOW()
+    WHERE id = $1
+"#;
+
+/// Count job $1's failure, or clear the count when $2 says it succeeded.
+/// Returns the failures before and after.
+pub const RECORD_OUTCOME: &str = r#"
+    UPDATE scheduled_jobs j
+    SET consecutive_failures = CASE WHEN $2 THEN 0 ELSE j.consecutive_failures + 1 END
+    FROM scheduled_jobs prior
+    WHERE j.name = $1 AND prior.name = j.name
+    RETURNING prior.consecutive_failures AS previous, j.consecutive_failures AS current
+"#;
+
+pub const LIST_RUNS: &str = r#"
+    SELECT id, job_name, trigger, status, instance_id, started_at, finished_at, output, error, triggered_by
+    FROM scheduled_job_runs
+    WHERE job_name = $1
+    ORDER BY started_at DESC
+    LIMIT $2
+"#;
+
+/// Runs still marked running after $1 seconds were left by a server that
+/// stopped mid-run
+pub const ABANDON_STALE_RUNS: &str = r#"
+    UPDATE scheduled_job_runs
+    SET status = 'failed', finished_at = NOW(), error = 'Abandoned: the server running it stopped'
+    WHERE status = 'running' AND started_at < NOW() - make_interval(secs => $1)
+"#;
+
+pub const PURGE_RUNS: &str = r#"
+    DELETE FROM scheduled_job_runs
+    WHERE status <> 'running' AND started_at < NOW() - make_interval(days => $1)
+"#;
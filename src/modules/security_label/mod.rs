@@ -0,0 +1,51 @@
+//! Security Label Module
+//!
+//! This module flags VIP and sensitive records including:
+//! - VIP, psychiatric, HIV and substance use labels as HL7 ActCodes in FHIR `meta.security`
+//! - `GET`/`PUT /{type}/{id}/security-labels` behind the manage-permissions permission
+//! - The labels of records and their patients for authorization, where a labelled record
+//!   needs a treating relationship or break-glass
+
+#[path = "security_label.controller.rs"]
+pub mod security_label_controller;
+#[path = "security_label.service.rs"]
+pub mod security_label_service;
+#[path = "security_label.sql.rs"]
+pub mod security_label_sql;
+
+pub use security_label_controller::SecurityLabelController;
+pub use security_label_service::{
+    labels_of, relabel, Labelled, RecordSecurityLabels, SecurityLabelService, SecurityLabels, LABELLED,
+};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Security Label Module Configuration
+pub struct SecurityLabelModule {
+    pub service: Arc<SecurityLabelService>,
+    pub controller: Arc<SecurityLabelController>,
+}
+
+impl SecurityLabelModule {
+    /// Create a new Security Label Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(SecurityLabelService::new(db_pool));
+        let controller = Arc::new(SecurityLabelController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register label routes for a FHIR resource type, to be merged into
+    /// that resource's router
+    pub fn routes(&self, resource_type: &'static str) -> ApiRouter {
+        self.controller.routes(resource_type)
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<SecurityLabelService> {
+        self.service.clone()
+    }
+}
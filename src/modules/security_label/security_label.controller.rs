@@ -0,0 +1,142 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::authorization::ConfidentialityLabel;
+use crate::modules::security_label::{SecurityLabelService, SecurityLabels};
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Reads and sets the confidentiality labels of one resource type
+pub struct SecurityLabelController {
+    security_label_service: Arc<SecurityLabelService>,
+}
+
+#[derive(Clone)]
+pub struct SecurityLabelState {
+    security_label_service: Arc<SecurityLabelService>,
+    /// FHIR type of the resources under this router
+    resource_type: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetSecurityLabelsRequest {
+    /// Label names or HL7 codes, e.g. `vip` or `PSY`; empty clears the labels
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl SecurityLabelController {
+    /// Create new controller with injected service
+    pub fn new(security_label_service: Arc<SecurityLabelService>) -> Self {
+        Self { security_label_service }
+    }
+
+    /// Label routes for one resource type, to be merged into that resource's
+    /// router
+    pub fn routes(&self, resource_type: &'static str) -> ApiRouter {
+        ApiRouter::new()
+            .get("/:id/security-labels", Self::get_labels, "Confidentiality labels of a resource")
+            .put("/:id/security-labels", Self::set_labels, "Replace the confidentiality labels of a resource")
+            .with_state(SecurityLabelState {
+                security_label_service: self.security_label_service.clone(),
+                resource_type,
+            })
+    }
+
+    pub async fn get_labels(
+        State(state): State<SecurityLabelState>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<SecurityLabels>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match state.security_label_service.labels(actor, state.resource_type, id).await {
+            Ok(Some(labels)) => Ok(Json(labels)),
+            Ok(None) => Err(Self::not_found(state.resource_type, id)),
+            Err(e) => Err(Self::error_response("Failed to get security labels", e)),
+        }
+    }
+
+    /// Flag a resource as VIP, psychiatric, HIV or substance use, or clear
+    /// its flags
+    pub async fn set_labels(
+        State(state): State<SecurityLabelState>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<SetSecurityLabelsRequest>,
+    ) -> Result<Json<SecurityLabels>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+        let labels = request
+            .labels
+            .iter()
+            .map(|label| {
+                ConfidentialityLabel::parse(label).ok_or_else(|| HimsError::ValidationError {
+                    message: format!("Unknown security label: {}", label),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Self::error_response("Invalid security labels", e))?;
+
+        match state.security_label_service.set_labels(actor, state.resource_type, id, &labels).await {
+            Ok(Some(labels)) => Ok(Json(labels)),
+            Ok(None) => Err(Self::not_found(state.resource_type, id)),
+            Err(e) => Err(Self::error_response("Failed to set security labels", e)),
+        }
+    }
+
+    fn not_found(resource_type: &str, id: Uuid) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Not found".to_string(),
+                message: format!("No {} with id {}", resource_type, id),
+            }),
+        )
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,321 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::ACT_CODE_SYSTEM;
+use crate::modules::authorization::{Action, AuthResult, ConfidentialityLabel, Resource, SecurityLabelSource};
+use crate::modules::role::RoleService;
+use crate::modules::security_label::security_label_sql;
+
+/// A FHIR resource type whose records can be labelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Labelled {
+    pub resource_type: &'static str,
+    pub table: &'static str,
+}
+
+pub const LABELLED: [Labelled; 2] = [
+    Labelled {
+        resource_type: "Patient",
+        table: "patients",
+    },
+    Labelled {
+        resource_type: "DocumentReference",
+        table: "medical_records",
+    },
+];
+
+impl Labelled {
+    pub fn find(resource_type: &str) -> Option<&'static Labelled> {
+        LABELLED.iter().find(|resource| resource.resource_type == resource_type)
+    }
+
+    /// `template` for this resource's table
+    fn sql(&self, template: &str) -> String {
+        template.replace("{table}", self.table)
+    }
+}
+
+/// Confidentiality labels of a resource
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityLabels {
+    pub resource_type: String,
+    pub id: Uuid,
+    pub version_id: String,
+    pub labels: Vec<ConfidentialityLabel>,
+}
+
+/// Confidentiality labels among the codings of a `meta.security`
+pub fn labels_of(security: &Value) -> Vec<ConfidentialityLabel> {
+    let mut labels: Vec<ConfidentialityLabel> = security
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|coding| coding["system"] == ACT_CODE_SYSTEM)
+        .filter_map(|coding| coding["code"].as_str().and_then(ConfidentialityLabel::from_code))
+        .collect();
+    labels.sort();
+    labels.dedup();
+    labels
+}
+
+/// `meta.security` with its confidentiality labels replaced by `labels`,
+/// keeping codings of other kinds
+pub fn relabel(security: &Value, labels: &[ConfidentialityLabel]) -> Value {
+    let mut codings: Vec<Value> = security
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|coding| labels_of(&json!([coding])).is_empty())
+        .cloned()
+        .collect();
+    let mut labels = labels.to_vec();
+    labels.sort();
+    labels.dedup();
+    codings.extend(labels.iter().map(|label| {
+        json!({
+            "system": ACT_CODE_SYSTEM,
+            "code": label.code(),
+            "display": label.display(),
+        })
+    }));
+    Value::Array(codings)
+}
+
+/// Security label service flagging VIP and sensitive records
+pub struct SecurityLabelService {
+    pool: PgPool,
+    role_service: RoleService,
+}
+
+impl SecurityLabelService {
+    /// Create new security label service
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Labels of a resource, `None` when no resource has the id. Labels
+    /// reveal what a record is about, so reading them takes the same
+    /// permission as setting them.
+    pub async fn labels(
+        &self,
+        actor: Uuid,
+        resource_type: &str,
+        id: Uuid,
+    ) -> Result<Option<SecurityLabels>, HimsError> {
+        let resource = Self::resource(resource_type)?;
+        self.require(actor, Action::ManagePermissions).await?;
+
+        let row = sqlx::query(&resource.sql(security_label_sql::GET_SECURITY))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let security: Value = row.try_get("security").map_err(database_error)?;
+        Ok(Some(SecurityLabels {
+            resource_type: resource.resource_type.to_string(),
+            id,
+            version_id: row.try_get("version_id").map_err(database_error)?,
+            labels: labels_of(&security),
+        }))
+    }
+
+    /// Replace a resource's confidentiality labels as a new version, keeping
+    /// its other security codings. `None` when no resource has the id.
+    pub async fn set_labels(
+        &self,
+        actor: Uuid,
+        resource_type: &str,
+        id: Uuid,
+        labels: &[ConfidentialityLabel],
+    ) -> Result<Option<SecurityLabels>, HimsError> {
+        let resource = Self::resource(resource_type)?;
+        self.require(actor, Action::ManagePermissions).await?;
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let security: Option<Value> = sqlx::query_scalar(&resource.sql(security_label_sql::GET_SECURITY_FOR_UPDATE))
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        let Some(security) = security else {
+            return Ok(None);
+        };
+
+        let security = relabel(&security, labels);
+        let version_id: String = sqlx::query_scalar(&resource.sql(security_label_sql::SET_SECURITY))
+            .bind(id)
+            .bind(&security)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+
+        let labels = labels_of(&security);
+        let codes: Vec<&str> = labels.iter().map(|label| label.code()).collect();
+        tracing::info!("User {} labelled {}/{} as [{}]", actor, resource_type, id, codes.join(", "));
+        Ok(Some(SecurityLabels {
+            resource_type: resource.resource_type.to_string(),
+            id,
+            version_id,
+            labels,
+        }))
+    }
+
+    fn resource(resource_type: &str) -> Result<&'static Labelled, HimsError> {
+        Labelled::find(resource_type).ok_or_else(|| HimsError::ValidationError {
+            message: format!("{} resources cannot be labelled", resource_type),
+        })
+    }
+
+    /// Require a permission of the actor
+    async fn require(&self, actor: Uuid, action: Action) -> Result<(), HimsError> {
+        if !self.role_service.get_user_permissions(actor).await?.contains(&action) {
+            tracing::warn!("User {} lacks {} permission", actor, action);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", action),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Confidentiality labels of patients and medical records, for authorization
+/// decisions. A record carries its own labels and its patient's.
+#[derive(Debug, Clone)]
+pub struct RecordSecurityLabels {
+    pool: PgPool,
+}
+
+impl RecordSecurityLabels {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Patient whose labels a resource carries, other than a record's own
+    fn patient_of(resource: &Resource, patient_id: Option<Uuid>) -> Option<Uuid> {
+        match resource {
+            Resource::Patient(id) => Some(*id),
+            Resource::MedicalRecord(_) => None,
+            _ => patient_id,
+        }
+    }
+}
+
+#[async_trait]
+impl SecurityLabelSource for RecordSecurityLabels {
+    async fn security_labels(
+        &self,
+        resource: &Resource,
+        patient_id: Option<Uuid>,
+    ) -> AuthResult<Vec<ConfidentialityLabel>> {
+        let mut labels = self.security_labels_batch(&[(resource.clone(), patient_id)]).await?;
+        Ok(labels.pop().unwrap_or_default())
+    }
+
+    /// Looks up the patients' labels in one query and the records' in another
+    async fn security_labels_batch(
+        &self,
+        resources: &[(Resource, Option<Uuid>)],
+    ) -> AuthResult<Vec<Vec<ConfidentialityLabel>>> {
+        let patient_ids: Vec<Uuid> = resources
+            .iter()
+            .filter_map(|(resource, patient_id)| Self::patient_of(resource, *patient_id))
+            .collect();
+        let record_ids: Vec<Uuid> = resources
+            .iter()
+            .filter_map(|(resource, _)| match resource {
+                Resource::MedicalRecord(id) => Some(*id),
+                _ => None,
+            })
+            .collect();
+
+        let mut patients: HashMap<Uuid, Vec<ConfidentialityLabel>> = HashMap::new();
+        if !patient_ids.is_empty() {
+            let rows = sqlx::query(security_label_sql::GET_PATIENTS_SECURITY)
+                .bind(&patient_ids)
+                .fetch_all(&self.pool)
+                .await?;
+            for row in rows {
+                let security: Option<Value> = row.try_get("security")?;
+                patients.insert(row.try_get("id")?, security.iter().flat_map(labels_of).collect());
+            }
+        }
+
+        let mut records: HashMap<Uuid, Vec<ConfidentialityLabel>> = HashMap::new();
+        if !record_ids.is_empty() {
+            let rows = sqlx::query(security_label_sql::GET_RECORDS_SECURITY)
+                .bind(&record_ids)
+                .fetch_all(&self.pool)
+                .await?;
+            for row in rows {
+                let mut labels = Vec::new();
+                for column in ["security", "patient_security"] {
+                    let security: Option<Value> = row.try_get(column)?;
+                    labels.extend(security.iter().flat_map(labels_of));
+                }
+                records.insert(row.try_get("id")?, labels);
+            }
+        }
+
+        Ok(resources
+            .iter()
+            .map(|(resource, patient_id)| {
+                let mut labels = match resource {
+                    Resource::MedicalRecord(id) => records.get(id).cloned().unwrap_or_default(),
+                    _ => Self::patient_of(resource, *patient_id)
+                        .and_then(|id| patients.get(&id).cloned())
+                        .unwrap_or_default(),
+                };
+                labels.sort();
+                labels.dedup();
+                labels
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::constants::{OBSERVATION_VALUE_SYSTEM, REDACTED_SECURITY_CODE};
+
+    #[test]
+    fn test_parse_confidentiality_label() {
+        assert_eq!(ConfidentialityLabel::parse("vip"), Some(ConfidentialityLabel::Vip));
+        assert_eq!(ConfidentialityLabel::parse(" psy "), Some(ConfidentialityLabel::Psychiatric));
+        assert_eq!(ConfidentialityLabel::parse("substance_abuse"), Some(ConfidentialityLabel::SubstanceAbuse));
+        assert_eq!(ConfidentialityLabel::from_code("HIV"), Some(ConfidentialityLabel::Hiv));
+        assert_eq!(ConfidentialityLabel::parse("R"), None);
+    }
+
+    #[test]
+    fn test_relabel_keeps_other_codings() {
+        let redacted = json!({ "system": OBSERVATION_VALUE_SYSTEM, "code": REDACTED_SECURITY_CODE });
+        let security = json!([redacted, { "system": ACT_CODE_SYSTEM, "code": "CEL" }]);
+        assert_eq!(labels_of(&security), vec![ConfidentialityLabel::Vip]);
+
+        let relabelled = relabel(&security, &[ConfidentialityLabel::Hiv, ConfidentialityLabel::Psychiatric]);
+        assert_eq!(relabelled[0], redacted);
+        assert_eq!(labels_of(&relabelled), vec![ConfidentialityLabel::Psychiatric, ConfidentialityLabel::Hiv]);
+
+        let cleared = relabel(&relabelled, &[]);
+        assert_eq!(cleared, json!([redacted]));
+        assert!(labels_of(&Value::Null).is_empty());
+    }
+}
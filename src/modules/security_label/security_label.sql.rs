@@ -0,0 +1,45 @@
+//! Security Label SQL Queries
+//!
+//! This file contains all SQL queries used by the security label service.
+//! `{table}` stands for a labelled resource's table, filled in from the
+//! service's fixed list of resources.
+
+/// `meta.security` and version of row $1
+pub const GET_SECURITY: &str = r#"
+    SELECT COALESCE(meta->'security', '[]'::jsonb) AS security, COALESCE(meta->>'version_id', '1') AS version_id
+    FROM {table}
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// `meta.security` of row $1, locked until it is relabelled
+pub const GET_SECURITY_FOR_UPDATE: &str = r#"
+    SELECT COALESCE(meta->'security', '[]'::jsonb) AS security
+    FROM {table}
+    WHERE id = $1 AND deleted_at IS NULL
+    FOR UPDATE
+"#;
+
+/// Replace row $1's `meta.security` with $2 as a new version
+pub const SET_SECURITY: &str = r#"
+    UPDATE {table}
+    SET meta = jsonb_set(
+            jsonb_set(jsonb_set(meta, '{security}', $2), '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1
+    RETURNING COALESCE(meta->>'version_id', '1') AS version_id
+"#;
+
+/// `meta.security` of patients $1
+pub const GET_PATIENTS_SECURITY: &str = r#"
+    SELECT id, meta->'security' AS security
+    FROM patients
+    WHERE id = ANY($1)
+"#;
+
+/// `meta.security` of medical records $1 and of their patients
+pub const GET_RECORDS_SECURITY: &str = r#"
+    SELECT r.id, r.meta->'security' AS security, p.meta->'security' AS patient_security
+    FROM medical_records r
+    LEFT JOIN patients p ON p.id = r.patient_id
+    WHERE r.id = ANY($1)
+"#;
@@ -0,0 +1,58 @@
+//! Service Account Module
+//! 
+//! This module provides machine-to-machine credentials for system integrations including:
+//! - Service accounts mapped to `Subject::System` with scoped actions
+//! - Hashed API keys with expiry, revocation and rotation grace periods
+//! - Per-key rate limits and scopes enforced on every request by the API key middleware
+//! - OAuth 2.0 client-credentials grant issuing short-lived tokens
+//! - Usage trail and audit events for every key
+
+#[path = "service_account.controller.rs"]
+pub mod service_account_controller;
+#[path = "service_account.service.rs"]
+pub mod service_account_service;
+#[path = "service_account.middleware.rs"]
+pub mod service_account_middleware;
+#[path = "service_account.sql.rs"]
+pub mod service_account_sql;
+
+pub use service_account_controller::ServiceAccountController;
+pub use service_account_service::{ServiceAccountConfig, ServiceAccountService, ServicePrincipal};
+pub use service_account_middleware::ApiKeyMiddleware;
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Service Account Module Configuration
+pub struct ServiceAccountModule {
+    pub service: Arc<ServiceAccountService>,
+    pub controller: Arc<ServiceAccountController>,
+    pub middleware: Arc<ApiKeyMiddleware>,
+}
+
+impl ServiceAccountModule {
+    /// Create a new Service Account Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(ServiceAccountService::new(db_pool));
+        let controller = Arc::new(ServiceAccountController::new(service.clone()));
+        let middleware = Arc::new(ApiKeyMiddleware::new(service.clone()));
+        
+        Self {
+            service,
+            controller,
+            middleware,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<ServiceAccountService> {
+        self.service.clone()
+    }
+}
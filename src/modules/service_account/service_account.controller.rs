@@ -0,0 +1,291 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::SessionClient;
+use crate::modules::service_account::service_account_service::{
+    ApiKey, ApiKeyUsage, CreateApiKey, CreateServiceAccount, IssuedApiKey, RotateApiKey, ServiceAccount,
+    ServiceToken, UpdateServiceAccount,
+};
+use crate::modules::service_account::ServiceAccountService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Service account controller for machine credentials administration
+pub struct ServiceAccountController {
+    service_account_service: Arc<ServiceAccountService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// OAuth 2.0 client-credentials token request (RFC 6749 section 4.4)
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub client_id: Uuid,
+    pub client_secret: String,
+    pub scope: Option<String>,
+}
+
+impl ServiceAccountController {
+    /// Create new controller with injected service
+    pub fn new(service_account_service: Arc<ServiceAccountService>) -> Self {
+        Self { service_account_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_account, "Create a service account")
+            .get("/", Self::list_accounts, "List service accounts")
+            .post("/token", Self::issue_token, "Client-credentials grant for system integrations")
+            .get("/:id", Self::get_account, "Get service account by ID")
+            .put("/:id", Self::update_account, "Update a service account's scopes or rate limit")
+            .delete("/:id", Self::deactivate_account, "Deactivate a service account and revoke its keys")
+            .get("/:id/usage", Self::get_usage, "Recent API key usage for a service account")
+            .post("/:id/keys", Self::create_key, "Issue an API key")
+            .get("/:id/keys", Self::list_keys, "List API keys of a service account")
+            .delete("/:id/keys/:key_id", Self::revoke_key, "Revoke an API key")
+            .post("/:id/keys/:key_id/rotate", Self::rotate_key, "Rotate an API key")
+            .with_state(self.service_account_service.clone())
+    }
+
+    /// Create a service account
+    pub async fn create_account(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Json(payload): Json<CreateServiceAccount>,
+    ) -> Result<(StatusCode, Json<ServiceAccount>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} creating service account {}", actor, payload.name);
+
+        match service_account_service.create_account(actor, payload).await {
+            Ok(account) => Ok((StatusCode::CREATED, Json(account))),
+            Err(e) => Err(Self::error_response("Failed to create service account", e)),
+        }
+    }
+
+    /// List service accounts
+    pub async fn list_accounts(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<ServiceAccount>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match service_account_service.list_accounts(actor).await {
+            Ok(accounts) => Ok(Json(accounts)),
+            Err(e) => Err(Self::error_response("Failed to list service accounts", e)),
+        }
+    }
+
+    /// Get service account by ID
+    pub async fn get_account(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<ServiceAccount>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match service_account_service.get_account(id).await {
+            Ok(Some(account)) => Ok(Json(account)),
+            Ok(None) => {
+                tracing::warn!("Service account not found: {}", id);
+                Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Service account not found".to_string(),
+                        message: format!("Service account with id {} not found", id),
+                    }),
+                ))
+            }
+            Err(e) => Err(Self::error_response("Failed to retrieve service account", e)),
+        }
+    }
+
+    /// Update a service account's scopes or rate limit
+    pub async fn update_account(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<UpdateServiceAccount>,
+    ) -> Result<Json<ServiceAccount>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match service_account_service.update_account(actor, id, payload).await {
+            Ok(account) => Ok(Json(account)),
+            Err(e) => Err(Self::error_response("Failed to update service account", e)),
+        }
+    }
+
+    /// Deactivate a service account and revoke its keys
+    pub async fn deactivate_account(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match service_account_service.deactivate_account(actor, id).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to deactivate service account", e)),
+        }
+    }
+
+    /// Recent API key usage for a service account
+    pub async fn get_usage(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Query(params): Query<UsageQuery>,
+    ) -> Result<Json<Vec<ApiKeyUsage>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match service_account_service
+            .get_usage(actor, id, params.since, params.limit.unwrap_or(100))
+            .await
+        {
+            Ok(usage) => Ok(Json(usage)),
+            Err(e) => Err(Self::error_response("Failed to retrieve API key usage", e)),
+        }
+    }
+
+    /// Issue an API key. The secret is returned only in this response.
+    pub async fn create_key(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<CreateApiKey>,
+    ) -> Result<(StatusCode, Json<IssuedApiKey>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} issuing API key for service account {}", actor, id);
+
+        match service_account_service.create_key(actor, id, payload).await {
+            Ok(issued) => Ok((StatusCode::CREATED, Json(issued))),
+            Err(e) => Err(Self::error_response("Failed to create API key", e)),
+        }
+    }
+
+    /// List API keys of a service account
+    pub async fn list_keys(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<ApiKey>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match service_account_service.list_keys(actor, id).await {
+            Ok(keys) => Ok(Json(keys)),
+            Err(e) => Err(Self::error_response("Failed to list API keys", e)),
+        }
+    }
+
+    /// Revoke an API key
+    pub async fn revoke_key(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path((id, key_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match service_account_service.revoke_key(actor, id, key_id).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to revoke API key", e)),
+        }
+    }
+
+    /// Rotate an API key; the old key remains valid for the grace period
+    pub async fn rotate_key(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        Path((id, key_id)): Path<(Uuid, Uuid)>,
+        payload: Option<Json<RotateApiKey>>,
+    ) -> Result<(StatusCode, Json<IssuedApiKey>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        let request = payload.map(|Json(request)| request).unwrap_or_default();
+
+        match service_account_service.rotate_key(actor, id, key_id, request).await {
+            Ok(issued) => Ok((StatusCode::CREATED, Json(issued))),
+            Err(e) => Err(Self::error_response("Failed to rotate API key", e)),
+        }
+    }
+
+    /// Client-credentials grant for system integrations
+    pub async fn issue_token(
+        State(service_account_service): State<Arc<ServiceAccountService>>,
+        client: SessionClient,
+        Json(payload): Json<TokenRequest>,
+    ) -> Result<Json<ServiceToken>, (StatusCode, Json<ErrorResponse>)> {
+        if payload.grant_type != "client_credentials" {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "unsupported_grant_type".to_string(),
+                    message: "Only the client_credentials grant is supported".to_string(),
+                }),
+            ));
+        }
+
+        match service_account_service
+            .client_credentials(
+                payload.client_id,
+                &payload.client_secret,
+                payload.scope.as_deref(),
+                client.ip_address.as_deref(),
+            )
+            .await
+        {
+            Ok(token) => Ok(Json(token)),
+            Err(e) => Err(Self::error_response("Failed to issue token", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::AuthenticationError { .. } => StatusCode::UNAUTHORIZED,
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::modules::auth::SessionClient;
+use crate::modules::service_account::service_account_service::ApiKeyRejection;
+use crate::modules::service_account::ServiceAccountService;
+
+/// Header carrying a service account API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// API key authentication middleware for system integrations
+pub struct ApiKeyMiddleware {
+    service: Arc<ServiceAccountService>,
+}
+
+impl ApiKeyMiddleware {
+    pub fn new(service: Arc<ServiceAccountService>) -> Self {
+        Self { service }
+    }
+
+    pub fn service(&self) -> Arc<ServiceAccountService> {
+        self.service.clone()
+    }
+
+    /// Authenticate requests presenting an `X-API-Key` header, applying the
+    /// key's rate limit and scopes to each request and recording its use.
+    /// Requests without one pass through to bearer-token authentication
+    /// unchanged. Uses are recorded against the client the rate limiter
+    /// resolved, so `X-Forwarded-For` only counts from a trusted proxy.
+    pub async fn authenticate(
+        State(service): State<Arc<ServiceAccountService>>,
+        headers: HeaderMap,
+        mut request: Request,
+        next: Next,
+    ) -> Result<Response, StatusCode> {
+        let Some(api_key) = headers.get(API_KEY_HEADER).and_then(|h| h.to_str().ok()) else {
+            return Ok(next.run(request).await);
+        };
+
+        let ip_address = SessionClient::from_request(request.extensions(), &headers).ip_address;
+        let principal = match service.authenticate_api_key(api_key, ip_address.as_deref()).await {
+            Ok(principal) => principal,
+            Err(ApiKeyRejection::Invalid) => {
+                tracing::warn!("Invalid or expired API key presented");
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            Err(ApiKeyRejection::RateLimited { retry_after_secs }) => {
+                let mut response = StatusCode::TOO_MANY_REQUESTS.into_response();
+                if let Ok(value) = HeaderValue::from_str(&retry_after_secs.to_string()) {
+                    response.headers_mut().insert(header::RETRY_AFTER, value);
+                }
+                return Ok(response);
+            }
+            Err(ApiKeyRejection::Error(e)) => {
+                tracing::error!("API key validation error: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+
+        let method = request.method().to_string();
+        let endpoint = request.uri().path().to_string();
+
+        let response = if !principal.allows_request(request.method(), &endpoint) {
+            tracing::warn!("Service account {} lacks the scope for {} {}", principal.account_name, method, endpoint);
+            StatusCode::FORBIDDEN.into_response()
+        } else {
+            let token = service.request_token(&principal).map_err(|e| {
+                tracing::error!("Failed to issue request token for API key: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            let bearer = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            // Handlers written against users see the account as an authenticated user
+            request.headers_mut().insert(header::AUTHORIZATION, bearer);
+            request.extensions_mut().insert(principal.to_user());
+            request.extensions_mut().insert(principal.clone());
+            next.run(request).await
+        };
+
+        service
+            .record_usage(&principal, &method, &endpoint, response.status().as_u16(), ip_address.as_deref())
+            .await;
+        Ok(response)
+    }
+}
@@ -0,0 +1,960 @@
+use axum::http::Method;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{AuditAction, AuditEventType, AuditLog, AuditOutcome};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::auth_jwt::token_verifier;
+use crate::modules::auth::AuthenticatedUser;
+use crate::modules::authorization::{Action, Subject};
+use crate::modules::role::RoleService;
+
+// Import SQL queries from separate file
+use crate::modules::service_account::service_account_sql::*;
+
+/// Prefix identifying HIMS API keys in headers, logs and secret scanners
+pub const API_KEY_PREFIX: &str = "hims_";
+/// Role claim carried by service account tokens
+pub const SERVICE_ACCOUNT_ROLE: &str = "service_account";
+/// Lifetime of the token an API key is exchanged for within one request
+const REQUEST_TOKEN_TTL_SECS: i64 = 300;
+
+/// Service account and API key configuration
+#[derive(Debug, Clone)]
+pub struct ServiceAccountConfig {
+    /// Requests per minute when an account does not set its own limit
+    pub default_rate_limit_per_minute: i32,
+    /// Longest lifetime a key may be issued with
+    pub max_key_lifetime_days: i64,
+    /// How long a rotated-out key keeps working
+    pub rotation_grace_hours: i64,
+    /// Lifetime of client-credentials access tokens
+    pub token_ttl_secs: i64,
+}
+
+impl Default for ServiceAccountConfig {
+    fn default() -> Self {
+        Self {
+            default_rate_limit_per_minute: 600,
+            max_key_lifetime_days: 365,
+            rotation_grace_hours: 24,
+            token_ttl_secs: 15 * 60,
+        }
+    }
+}
+
+/// Machine principal used by system integrations
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceAccount {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub scopes: Vec<Action>,
+    pub rate_limit_per_minute: i32,
+    pub is_active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// API key metadata. The secret is only returned once, at creation.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub service_account_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Option<Vec<Action>>,
+    pub rate_limit_per_minute: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub rotated_from: Option<Uuid>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_ip: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Newly created API key together with its secret
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    pub api_key: String,
+}
+
+/// One request made with an API key
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyUsage {
+    pub id: Uuid,
+    pub api_key_id: Uuid,
+    pub method: String,
+    pub endpoint: String,
+    pub status_code: i32,
+    pub ip_address: Option<String>,
+    pub used_at: DateTime<Utc>,
+}
+
+/// Request to create a service account
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateServiceAccount {
+    pub name: String,
+    pub description: Option<String>,
+    /// Action names the account may exercise
+    pub scopes: Vec<String>,
+    pub rate_limit_per_minute: Option<i32>,
+}
+
+/// Request to change a service account's scopes or rate limit
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateServiceAccount {
+    pub description: Option<String>,
+    pub scopes: Vec<String>,
+    pub rate_limit_per_minute: Option<i32>,
+}
+
+/// Request to issue an API key
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    /// Narrows the account scopes for this key
+    pub scopes: Option<Vec<String>>,
+    pub rate_limit_per_minute: Option<i32>,
+    pub expires_in_days: Option<i64>,
+}
+
+/// Request to rotate an API key
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RotateApiKey {
+    /// How long the old key keeps working; defaults to the configured grace period
+    pub grace_period_hours: Option<i64>,
+    pub expires_in_days: Option<i64>,
+}
+
+/// Access token returned by the client-credentials grant
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+}
+
+/// Authenticated service account, attached to requests by the API key middleware
+#[derive(Debug, Clone)]
+pub struct ServicePrincipal {
+    pub account_id: Uuid,
+    pub account_name: String,
+    pub key_id: Uuid,
+    pub scopes: HashSet<Action>,
+    pub rate_limit_per_minute: u32,
+}
+
+impl ServicePrincipal {
+    /// Authorization subject for relationship checks
+    pub fn subject(&self) -> Subject {
+        Subject::System(self.account_id.to_string())
+    }
+
+    pub fn allows(&self, action: &Action) -> bool {
+        self.scopes.contains(action)
+    }
+
+    /// Whether the key's scopes cover a request: reads and searches need
+    /// `read` or `search`, creates `create` or `write`, updates `update` or
+    /// `write`, deletes `delete`, and device readings `record_device_data`
+    pub fn allows_request(&self, method: &Method, path: &str) -> bool {
+        let needed: &[Action] = match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => &[Action::Read, Action::Search],
+            Method::POST if path.ends_with("/_search") => &[Action::Read, Action::Search],
+            Method::POST if path.starts_with("/api/v1/devices/") => &[Action::RecordDeviceData],
+            Method::POST => &[Action::Create, Action::Write],
+            Method::PUT | Method::PATCH => &[Action::Update, Action::Write],
+            Method::DELETE => &[Action::Delete],
+            _ => &[],
+        };
+        needed.iter().any(|action| self.allows(action))
+    }
+
+    /// Present the principal to code written against authenticated users
+    pub fn to_user(&self) -> AuthenticatedUser {
+        let mut permissions: Vec<String> = self.scopes.iter().map(|a| a.to_string()).collect();
+        permissions.sort();
+        AuthenticatedUser {
+            id: self.account_id.to_string(),
+            username: self.account_name.clone(),
+            role: SERVICE_ACCOUNT_ROLE.to_string(),
+            permissions,
+        }
+    }
+}
+
+/// Why an API key was not accepted
+#[derive(Debug)]
+pub enum ApiKeyRejection {
+    Invalid,
+    RateLimited { retry_after_secs: u64 },
+    Error(HimsError),
+}
+
+/// Fixed one-minute request windows per API key
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    windows: Mutex<HashMap<Uuid, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    const WINDOW_SECS: u64 = 60;
+
+    /// Count a request; returns the seconds until the window resets when over the limit
+    pub fn check(&self, key_id: Uuid, limit: u32, now: Instant) -> Result<(), u64> {
+        let Ok(mut windows) = self.windows.lock() else {
+            return Ok(());
+        };
+        if windows.len() > 10_000 {
+            windows.retain(|_, (started, _)| now.duration_since(*started).as_secs() < Self::WINDOW_SECS);
+        }
+
+        let window = windows.entry(key_id).or_insert((now, 0));
+        let elapsed = now.duration_since(window.0).as_secs();
+        if elapsed >= Self::WINDOW_SECS {
+            *window = (now, 0);
+        }
+        if window.1 >= limit {
+            return Err(Self::WINDOW_SECS - now.duration_since(window.0).as_secs());
+        }
+        window.1 += 1;
+        Ok(())
+    }
+}
+
+/// Service for service accounts, API keys and client-credentials tokens
+pub struct ServiceAccountService {
+    pool: PgPool,
+    role_service: RoleService,
+    audit_service: AuditService,
+    config: ServiceAccountConfig,
+    rate_limiter: RateLimiter,
+    rng: SystemRandom,
+}
+
+impl ServiceAccountService {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_config(pool, ServiceAccountConfig::default())
+    }
+
+    pub fn with_config(pool: PgPool, config: ServiceAccountConfig) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            audit_service: AuditService::new(pool.clone()),
+            pool,
+            config,
+            rate_limiter: RateLimiter::default(),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Create a service account. Admins cannot grant scopes they do not hold.
+    pub async fn create_account(&self, actor: Uuid, request: CreateServiceAccount) -> Result<ServiceAccount, HimsError> {
+        let held = self.require_admin(actor).await?;
+        Self::validate_name(&request.name)?;
+        let scopes = Self::parse_scopes(&request.scopes)?;
+        Self::check_escalation(&held, &scopes)?;
+        let rate_limit = self.validate_rate_limit(request.rate_limit_per_minute)?;
+
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        sqlx::query(INSERT_SERVICE_ACCOUNT)
+            .bind(id)
+            .bind(&request.name)
+            .bind(&request.description)
+            .bind(Self::scopes_to_json(&scopes))
+            .bind(rate_limit)
+            .bind(actor)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.audit(actor, id, AuditAction::Create, format!("Created service account '{}'", request.name)).await;
+
+        Ok(ServiceAccount {
+            id,
+            name: request.name,
+            description: request.description,
+            scopes,
+            rate_limit_per_minute: rate_limit,
+            is_active: true,
+            created_by: Some(actor),
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// List all service accounts
+    pub async fn list_accounts(&self, actor: Uuid) -> Result<Vec<ServiceAccount>, HimsError> {
+        self.require_admin(actor).await?;
+
+        let rows = sqlx::query(LIST_SERVICE_ACCOUNTS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_account).collect())
+    }
+
+    /// Get service account by ID
+    pub async fn get_account(&self, id: Uuid) -> Result<Option<ServiceAccount>, HimsError> {
+        let row = sqlx::query(GET_SERVICE_ACCOUNT_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.as_ref().map(Self::row_to_account))
+    }
+
+    /// Change a service account's scopes or rate limit. Existing tokens keep
+    /// their scopes until they expire.
+    pub async fn update_account(
+        &self,
+        actor: Uuid,
+        id: Uuid,
+        request: UpdateServiceAccount,
+    ) -> Result<ServiceAccount, HimsError> {
+        let held = self.require_admin(actor).await?;
+        let existing = self.get_active_account(id).await?;
+        let scopes = Self::parse_scopes(&request.scopes)?;
+        Self::check_escalation(&held, &existing.scopes)?;
+        Self::check_escalation(&held, &scopes)?;
+        let rate_limit = self.validate_rate_limit(request.rate_limit_per_minute)?;
+
+        sqlx::query(UPDATE_SERVICE_ACCOUNT)
+            .bind(id)
+            .bind(&request.description)
+            .bind(Self::scopes_to_json(&scopes))
+            .bind(rate_limit)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.audit(actor, id, AuditAction::Update, format!("Updated service account '{}'", existing.name)).await;
+
+        Ok(ServiceAccount {
+            description: request.description,
+            scopes,
+            rate_limit_per_minute: rate_limit,
+            updated_at: Utc::now(),
+            ..existing
+        })
+    }
+
+    /// Deactivate a service account and revoke all of its keys
+    pub async fn deactivate_account(&self, actor: Uuid, id: Uuid) -> Result<(), HimsError> {
+        self.require_admin(actor).await?;
+        let account = self.get_active_account(id).await?;
+
+        let mut tx = self.pool.begin().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        sqlx::query(DEACTIVATE_SERVICE_ACCOUNT)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        sqlx::query(REVOKE_ACCOUNT_API_KEYS)
+            .bind(id)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        tx.commit().await.map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("Service account {} deactivated by {}", account.name, actor);
+        self.audit(actor, id, AuditAction::Delete, format!("Deactivated service account '{}'", account.name)).await;
+        Ok(())
+    }
+
+    /// Issue a new API key for a service account
+    pub async fn create_key(&self, actor: Uuid, account_id: Uuid, request: CreateApiKey) -> Result<IssuedApiKey, HimsError> {
+        self.require_admin(actor).await?;
+        let account = self.get_active_account(account_id).await?;
+        self.issue_key(actor, &account, request, None).await
+    }
+
+    /// List API keys of a service account
+    pub async fn list_keys(&self, actor: Uuid, account_id: Uuid) -> Result<Vec<ApiKey>, HimsError> {
+        self.require_admin(actor).await?;
+
+        let rows = sqlx::query(LIST_API_KEYS)
+            .bind(account_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_key).collect())
+    }
+
+    /// Revoke an API key immediately
+    pub async fn revoke_key(&self, actor: Uuid, account_id: Uuid, key_id: Uuid) -> Result<(), HimsError> {
+        self.require_admin(actor).await?;
+
+        let result = sqlx::query(REVOKE_API_KEY)
+            .bind(key_id)
+            .bind(account_id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::key_not_found(key_id));
+        }
+
+        tracing::info!("API key {} revoked by {}", key_id, actor);
+        self.audit(actor, account_id, AuditAction::Delete, format!("Revoked API key {}", key_id)).await;
+        Ok(())
+    }
+
+    /// Replace an API key. The old key keeps working for a grace period so
+    /// interface engines can be reconfigured without downtime.
+    pub async fn rotate_key(
+        &self,
+        actor: Uuid,
+        account_id: Uuid,
+        key_id: Uuid,
+        request: RotateApiKey,
+    ) -> Result<IssuedApiKey, HimsError> {
+        self.require_admin(actor).await?;
+        let account = self.get_active_account(account_id).await?;
+        let old = self.get_key(key_id).await?
+            .filter(|key| key.service_account_id == account_id && key.revoked_at.is_none())
+            .ok_or_else(|| Self::key_not_found(key_id))?;
+
+        let grace_hours = request.grace_period_hours.unwrap_or(self.config.rotation_grace_hours);
+        if !(0..=24 * 7).contains(&grace_hours) {
+            return Err(HimsError::ValidationError {
+                message: "Grace period must be between 0 and 168 hours".to_string(),
+            });
+        }
+
+        let replacement = CreateApiKey {
+            name: old.name.clone(),
+            scopes: old.scopes.as_ref().map(|scopes| scopes.iter().map(|a| a.to_string()).collect()),
+            rate_limit_per_minute: old.rate_limit_per_minute,
+            expires_in_days: request.expires_in_days,
+        };
+        let issued = self.issue_key(actor, &account, replacement, Some(old.id)).await?;
+
+        sqlx::query(EXPIRE_API_KEY)
+            .bind(old.id)
+            .bind(Utc::now() + Duration::hours(grace_hours))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!("API key {} rotated to {} by {}", old.id, issued.key.id, actor);
+        Ok(issued)
+    }
+
+    /// Recent requests made by a service account
+    pub async fn get_usage(
+        &self,
+        actor: Uuid,
+        account_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<ApiKeyUsage>, HimsError> {
+        self.require_admin(actor).await?;
+
+        let rows = sqlx::query(GET_ACCOUNT_USAGE)
+            .bind(account_id)
+            .bind(since.unwrap_or_else(|| Utc::now() - Duration::days(7)))
+            .bind(limit.clamp(1, 1000))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ApiKeyUsage {
+                id: row.get("id"),
+                api_key_id: row.get("api_key_id"),
+                method: row.get("method"),
+                endpoint: row.get("endpoint"),
+                status_code: row.get("status_code"),
+                ip_address: row.get("ip_address"),
+                used_at: row.get("used_at"),
+            })
+            .collect())
+    }
+
+    /// Resolve an API key to its principal and apply the per-key rate limit
+    pub async fn authenticate_api_key(
+        &self,
+        api_key: &str,
+        ip_address: Option<&str>,
+    ) -> Result<ServicePrincipal, ApiKeyRejection> {
+        if key_prefix(api_key).is_none() {
+            return Err(ApiKeyRejection::Invalid);
+        }
+
+        let now = Utc::now();
+        let row = sqlx::query(GET_ACTIVE_API_KEY_BY_HASH)
+            .bind(hash_api_key(api_key))
+            .bind(now)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ApiKeyRejection::Error(HimsError::DatabaseError(e.to_string())))?
+            .ok_or(ApiKeyRejection::Invalid)?;
+
+        let account_scopes = Self::scopes_from_json(row.get("account_scopes"));
+        // A key may narrow, never widen, its account's scopes
+        let scopes = match row.get::<Option<serde_json::Value>, _>("key_scopes") {
+            Some(key_scopes) => Self::scopes_from_json(key_scopes)
+                .into_iter()
+                .filter(|a| account_scopes.contains(a))
+                .collect(),
+            None => account_scopes,
+        };
+        let rate_limit = row
+            .get::<Option<i32>, _>("key_rate_limit")
+            .unwrap_or_else(|| row.get("account_rate_limit"));
+
+        let principal = ServicePrincipal {
+            account_id: row.get("account_id"),
+            account_name: row.get("account_name"),
+            key_id: row.get("key_id"),
+            scopes: scopes.into_iter().collect(),
+            rate_limit_per_minute: rate_limit.max(1) as u32,
+        };
+
+        if let Err(retry_after_secs) = self.rate_limiter.check(principal.key_id, principal.rate_limit_per_minute, Instant::now()) {
+            tracing::warn!("Service account {} exceeded its rate limit", principal.account_name);
+            return Err(ApiKeyRejection::RateLimited { retry_after_secs });
+        }
+
+        if let Err(e) = sqlx::query(TOUCH_API_KEY)
+            .bind(principal.key_id)
+            .bind(now)
+            .bind(ip_address)
+            .execute(&self.pool)
+            .await
+        {
+            tracing::error!("Failed to record API key use: {}", e);
+        }
+
+        Ok(principal)
+    }
+
+    /// Bearer token standing in for an API key for the rest of one request,
+    /// so handlers that read the caller from headers see the service account
+    pub fn request_token(&self, principal: &ServicePrincipal) -> Result<String, HimsError> {
+        let verifier = token_verifier().ok_or_else(|| HimsError::ConfigurationError {
+            message: "Token issuer not initialised".to_string(),
+        })?;
+        verifier.issue_service_token(&principal.to_user(), REQUEST_TOKEN_TTL_SECS)
+    }
+
+    /// Record a request made with an API key. Failures are logged, not raised.
+    pub async fn record_usage(
+        &self,
+        principal: &ServicePrincipal,
+        method: &str,
+        endpoint: &str,
+        status_code: u16,
+        ip_address: Option<&str>,
+    ) {
+        let result = sqlx::query(INSERT_API_KEY_USAGE)
+            .bind(Uuid::new_v4())
+            .bind(principal.key_id)
+            .bind(principal.account_id)
+            .bind(method)
+            .bind(endpoint)
+            .bind(status_code as i32)
+            .bind(ip_address)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await;
+
+        if let Err(e) = result {
+            tracing::error!("Failed to record API key usage: {}", e);
+        }
+    }
+
+    /// OAuth 2.0 client-credentials grant: the client ID is the service
+    /// account ID and the client secret is one of its API keys
+    pub async fn client_credentials(
+        &self,
+        client_id: Uuid,
+        client_secret: &str,
+        requested_scope: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<ServiceToken, HimsError> {
+        let invalid_client = || HimsError::AuthenticationError {
+            message: "Invalid client credentials".to_string(),
+        };
+
+        let mut principal = match self.authenticate_api_key(client_secret, ip_address).await {
+            Ok(principal) if principal.account_id == client_id => principal,
+            Ok(_) | Err(ApiKeyRejection::Invalid) => {
+                self.audit_token(client_id, AuditOutcome::MinorFailure, ip_address, "Client credentials rejected").await;
+                return Err(invalid_client());
+            }
+            Err(ApiKeyRejection::RateLimited { retry_after_secs }) => {
+                return Err(HimsError::SecurityError {
+                    message: format!("Rate limit exceeded; retry after {} seconds", retry_after_secs),
+                });
+            }
+            Err(ApiKeyRejection::Error(e)) => return Err(e),
+        };
+
+        if let Some(requested) = requested_scope {
+            let requested = Self::parse_scopes(
+                &requested.split_whitespace().map(str::to_string).collect::<Vec<_>>(),
+            )?;
+            if let Some(action) = requested.iter().find(|a| !principal.allows(a)) {
+                return Err(HimsError::SecurityError {
+                    message: format!("Scope not granted to this client: {}", action),
+                });
+            }
+            principal.scopes = requested.into_iter().collect();
+        }
+
+        let verifier = token_verifier().ok_or_else(|| HimsError::ConfigurationError {
+            message: "Token issuer not initialised".to_string(),
+        })?;
+        let user = principal.to_user();
+        let access_token = verifier.issue_service_token(&user, self.config.token_ttl_secs)?;
+
+        self.audit_token(client_id, AuditOutcome::Success, ip_address, "Client credentials token issued").await;
+
+        Ok(ServiceToken {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.config.token_ttl_secs,
+            scope: user.permissions.join(" "),
+        })
+    }
+
+    async fn issue_key(
+        &self,
+        actor: Uuid,
+        account: &ServiceAccount,
+        request: CreateApiKey,
+        rotated_from: Option<Uuid>,
+    ) -> Result<IssuedApiKey, HimsError> {
+        if request.name.trim().is_empty() || request.name.len() > 100 {
+            return Err(HimsError::ValidationError {
+                message: "API key name must be 1-100 characters".to_string(),
+            });
+        }
+
+        let scopes = match &request.scopes {
+            Some(names) => {
+                let scopes = Self::parse_scopes(names)?;
+                if let Some(action) = scopes.iter().find(|a| !account.scopes.contains(a)) {
+                    return Err(HimsError::ValidationError {
+                        message: format!("Key scope {} is not granted to the service account", action),
+                    });
+                }
+                Some(scopes)
+            }
+            None => None,
+        };
+        let rate_limit = match request.rate_limit_per_minute {
+            Some(limit) => Some(self.validate_rate_limit(Some(limit))?),
+            None => None,
+        };
+
+        let lifetime_days = request.expires_in_days.unwrap_or(self.config.max_key_lifetime_days);
+        if lifetime_days < 1 || lifetime_days > self.config.max_key_lifetime_days {
+            return Err(HimsError::ValidationError {
+                message: format!("Key lifetime must be between 1 and {} days", self.config.max_key_lifetime_days),
+            });
+        }
+
+        let api_key = self.generate_key()?;
+        let prefix = key_prefix(&api_key).unwrap_or_default().to_string();
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        let expires_at = now + Duration::days(lifetime_days);
+
+        sqlx::query(INSERT_API_KEY)
+            .bind(id)
+            .bind(account.id)
+            .bind(&request.name)
+            .bind(&prefix)
+            .bind(hash_api_key(&api_key))
+            .bind(scopes.as_deref().map(Self::scopes_to_json))
+            .bind(rate_limit)
+            .bind(expires_at)
+            .bind(rotated_from)
+            .bind(actor)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let action = if rotated_from.is_some() { "Rotated" } else { "Issued" };
+        self.audit(actor, account.id, AuditAction::Create, format!("{} API key {} ({})", action, id, prefix)).await;
+
+        Ok(IssuedApiKey {
+            key: ApiKey {
+                id,
+                service_account_id: account.id,
+                name: request.name,
+                key_prefix: prefix,
+                scopes,
+                rate_limit_per_minute: rate_limit,
+                expires_at: Some(expires_at),
+                revoked_at: None,
+                rotated_from,
+                last_used_at: None,
+                last_used_ip: None,
+                created_by: Some(actor),
+                created_at: now,
+            },
+            api_key,
+        })
+    }
+
+    async fn get_key(&self, id: Uuid) -> Result<Option<ApiKey>, HimsError> {
+        let row = sqlx::query(GET_API_KEY_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(row.as_ref().map(Self::row_to_key))
+    }
+
+    async fn get_active_account(&self, id: Uuid) -> Result<ServiceAccount, HimsError> {
+        self.get_account(id)
+            .await?
+            .filter(|account| account.is_active)
+            .ok_or_else(|| HimsError::ValidationError {
+                message: format!("Service account with id {} not found", id),
+            })
+    }
+
+    /// Service accounts are administered by users holding manage_users
+    async fn require_admin(&self, actor: Uuid) -> Result<HashSet<Action>, HimsError> {
+        let held = self.role_service.get_user_permissions(actor).await?;
+        if !held.contains(&Action::ManageUsers) {
+            tracing::warn!("User {} lacks {} permission", actor, Action::ManageUsers);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", Action::ManageUsers),
+            });
+        }
+        Ok(held)
+    }
+
+    fn check_escalation(held: &HashSet<Action>, scopes: &[Action]) -> Result<(), HimsError> {
+        match scopes.iter().find(|a| !held.contains(a)) {
+            Some(action) => Err(HimsError::SecurityError {
+                message: format!("Cannot grant scope not held by the actor: {}", action),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_rate_limit(&self, limit: Option<i32>) -> Result<i32, HimsError> {
+        let limit = limit.unwrap_or(self.config.default_rate_limit_per_minute);
+        if !(1..=100_000).contains(&limit) {
+            return Err(HimsError::ValidationError {
+                message: "Rate limit must be between 1 and 100000 requests per minute".to_string(),
+            });
+        }
+        Ok(limit)
+    }
+
+    fn validate_name(name: &str) -> Result<(), HimsError> {
+        let valid = !name.is_empty()
+            && name.len() <= 100
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+        if valid {
+            Ok(())
+        } else {
+            Err(HimsError::ValidationError {
+                message: "Service account name must be 1-100 characters of letters, digits, '_', '-' or '.'".to_string(),
+            })
+        }
+    }
+
+    /// Scopes must be known actions; custom actions are not grantable to machines
+    fn parse_scopes(names: &[String]) -> Result<Vec<Action>, HimsError> {
+        let mut scopes = Vec::new();
+        for name in names {
+            match Action::from_str(name) {
+                Ok(Action::Custom(_)) | Err(_) => {
+                    return Err(HimsError::ValidationError {
+                        message: format!("Unknown scope: {}", name),
+                    });
+                }
+                Ok(action) if !scopes.contains(&action) => scopes.push(action),
+                Ok(_) => {}
+            }
+        }
+        Ok(scopes)
+    }
+
+    fn generate_key(&self) -> Result<String, HimsError> {
+        let mut prefix = [0u8; 4];
+        let mut secret = [0u8; 32];
+        self.rng
+            .fill(&mut prefix)
+            .and_then(|_| self.rng.fill(&mut secret))
+            .map_err(|_| HimsError::InternalError { message: "Failed to generate API key".to_string() })?;
+
+        let prefix: String = prefix.iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(format!("{}{}_{}", API_KEY_PREFIX, prefix, general_purpose::URL_SAFE_NO_PAD.encode(secret)))
+    }
+
+    /// Audit failures must not block administration
+    async fn audit(&self, actor: Uuid, account_id: Uuid, action: AuditAction, details: String) {
+        let log = AuditLog::new(AuditEventType::SystemAccess, action, "ServiceAccount".to_string())
+            .with_user(actor)
+            .with_resource(account_id)
+            .with_details(details);
+        if let Err(e) = self.audit_service.create_audit_log(&log).await {
+            tracing::error!("Failed to write service account audit event: {}", e);
+        }
+    }
+
+    async fn audit_token(&self, client_id: Uuid, outcome: AuditOutcome, ip_address: Option<&str>, details: &str) {
+        let log = AuditLog::authentication_event(Some(client_id), outcome)
+            .with_source_info(ip_address.map(str::to_string), None)
+            .with_details(details.to_string());
+        if let Err(e) = self.audit_service.create_audit_log(&log).await {
+            tracing::error!("Failed to write service account audit event: {}", e);
+        }
+    }
+
+    fn key_not_found(id: Uuid) -> HimsError {
+        HimsError::ValidationError {
+            message: format!("API key with id {} not found", id),
+        }
+    }
+
+    fn scopes_to_json(scopes: &[Action]) -> serde_json::Value {
+        serde_json::Value::Array(
+            scopes.iter().map(|a| serde_json::Value::String(a.to_string())).collect(),
+        )
+    }
+
+    fn scopes_from_json(value: serde_json::Value) -> Vec<Action> {
+        value
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .filter_map(|s| Action::from_str(s).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn row_to_account(row: &sqlx::postgres::PgRow) -> ServiceAccount {
+        ServiceAccount {
+            id: row.get("id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            scopes: Self::scopes_from_json(row.get("scopes")),
+            rate_limit_per_minute: row.get("rate_limit_per_minute"),
+            is_active: row.get("is_active"),
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    fn row_to_key(row: &sqlx::postgres::PgRow) -> ApiKey {
+        ApiKey {
+            id: row.get("id"),
+            service_account_id: row.get("service_account_id"),
+            name: row.get("name"),
+            key_prefix: row.get("key_prefix"),
+            scopes: row.get::<Option<serde_json::Value>, _>("scopes").map(Self::scopes_from_json),
+            rate_limit_per_minute: row.get("rate_limit_per_minute"),
+            expires_at: row.get("expires_at"),
+            revoked_at: row.get("revoked_at"),
+            rotated_from: row.get("rotated_from"),
+            last_used_at: row.get("last_used_at"),
+            last_used_ip: row.get("last_used_ip"),
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+/// Displayable prefix of a well-formed API key (`hims_<8 hex>_<secret>`)
+pub fn key_prefix(api_key: &str) -> Option<&str> {
+    let rest = api_key.strip_prefix(API_KEY_PREFIX)?;
+    let (id, secret) = rest.split_once('_')?;
+    let well_formed = id.len() == 8 && id.chars().all(|c| c.is_ascii_hexdigit()) && secret.len() >= 32;
+    well_formed.then(|| &api_key[..API_KEY_PREFIX.len() + id.len()])
+}
+
+/// API keys are stored hashed; only the client holds the raw value
+pub fn hash_api_key(api_key: &str) -> String {
+    let digest = Sha256::digest(api_key.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_format_and_rate_limit() {
+        let key = "hims_0a1b2c3d_c2VjcmV0LXNlY3JldC1zZWNyZXQtc2VjcmV0LXNlY3JldA";
+        assert_eq!(key_prefix(key), Some("hims_0a1b2c3d"));
+        assert_eq!(key_prefix("hims_0a1b2c3d_short"), None);
+        assert_eq!(key_prefix("Bearer abc"), None);
+        assert_eq!(hash_api_key(key).len(), 64);
+
+        let limiter = RateLimiter::default();
+        let key_id = Uuid::new_v4();
+        let start = Instant::now();
+        assert!(limiter.check(key_id, 2, start).is_ok());
+        assert!(limiter.check(key_id, 2, start).is_ok());
+        assert_eq!(limiter.check(key_id, 2, start), Err(60));
+        assert!(limiter.check(key_id, 2, start + std::time::Duration::from_secs(61)).is_ok());
+    }
+
+    #[test]
+    fn test_scopes_cover_requests() {
+        let principal = ServicePrincipal {
+            account_id: Uuid::new_v4(),
+            account_name: "lab-interface".to_string(),
+            key_id: Uuid::new_v4(),
+            scopes: [Action::Read, Action::Create].into_iter().collect(),
+            rate_limit_per_minute: 60,
+        };
+        assert!(principal.allows_request(&Method::GET, "/api/v1/patients"));
+        assert!(principal.allows_request(&Method::POST, "/api/v1/patients/_search"));
+        assert!(principal.allows_request(&Method::POST, "/api/v1/medical-records"));
+        assert!(!principal.allows_request(&Method::PUT, "/api/v1/patients/1"));
+        assert!(!principal.allows_request(&Method::DELETE, "/api/v1/patients/1"));
+        assert!(!principal.allows_request(&Method::TRACE, "/api/v1/patients"));
+
+        let writer = ServicePrincipal { scopes: [Action::Write].into_iter().collect(), ..principal };
+        assert!(writer.allows_request(&Method::PATCH, "/api/v1/patients/1"));
+        assert!(!writer.allows_request(&Method::GET, "/api/v1/patients"));
+        assert!(!writer.allows_request(&Method::POST, "/api/v1/devices/readings"));
+
+        let monitor = ServicePrincipal { scopes: [Action::RecordDeviceData].into_iter().collect(), ..writer };
+        assert!(monitor.allows_request(&Method::POST, "/api/v1/devices/hl7"));
+        assert!(!monitor.allows_request(&Method::POST, "/api/v1/vital-signs"));
+    }
+}
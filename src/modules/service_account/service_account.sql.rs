@@ -0,0 +1,121 @@
+//! Service Account SQL Queries
+//!
+//! This file contains all SQL queries used by the service account service
+//! for clean separation of concerns and better maintainability.
+
+/// Insert a new service account
+pub const INSERT_SERVICE_ACCOUNT: &str = r#"
+    INSERT INTO service_accounts (id, name, description, scopes, rate_limit_per_minute, created_by, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+"#;
+
+/// Get service account by ID
+pub const GET_SERVICE_ACCOUNT_BY_ID: &str = r#"
+    SELECT id, name, description, scopes, rate_limit_per_minute, is_active, created_by, created_at, updated_at
+    FROM service_accounts
+    WHERE id = $1
+"#;
+
+/// List service accounts
+pub const LIST_SERVICE_ACCOUNTS: &str = r#"
+    SELECT id, name, description, scopes, rate_limit_per_minute, is_active, created_by, created_at, updated_at
+    FROM service_accounts
+    ORDER BY name
+"#;
+
+/// Update scopes and rate limit of a service account
+pub const UPDATE_SERVICE_ACCOUNT: &str = r#"
+    UPDATE service_accounts
+    SET description = $2, scopes = $3, rate_limit_per_minute = $4
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Deactivate a service account
+pub const DEACTIVATE_SERVICE_ACCOUNT: &str = r#"
+    UPDATE service_accounts
+    SET is_active = false
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Insert a new API key
+pub const INSERT_API_KEY: &str = r#"
+    INSERT INTO api_keys (
+        id, service_account_id, name, key_prefix, key_hash, scopes,
+        rate_limit_per_minute, expires_at, rotated_from, created_by, created_at
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+    )
+"#;
+
+/// Get API key by ID
+pub const GET_API_KEY_BY_ID: &str = r#"
+    SELECT id, service_account_id, name, key_prefix, scopes, rate_limit_per_minute, expires_at,
+           revoked_at, rotated_from, last_used_at, host(last_used_ip) AS last_used_ip, created_by, created_at
+    FROM api_keys
+    WHERE id = $1
+"#;
+
+/// List API keys of a service account
+pub const LIST_API_KEYS: &str = r#"
+    SELECT id, service_account_id, name, key_prefix, scopes, rate_limit_per_minute, expires_at,
+           revoked_at, rotated_from, last_used_at, host(last_used_ip) AS last_used_ip, created_by, created_at
+    FROM api_keys
+    WHERE service_account_id = $1
+    ORDER BY created_at DESC
+"#;
+
+/// Resolve a presented key hash to a usable key and its account
+pub const GET_ACTIVE_API_KEY_BY_HASH: &str = r#"
+    SELECT k.id AS key_id, k.scopes AS key_scopes, k.rate_limit_per_minute AS key_rate_limit,
+           a.id AS account_id, a.name AS account_name, a.scopes AS account_scopes,
+           a.rate_limit_per_minute AS account_rate_limit
+    FROM api_keys k
+    JOIN service_accounts a ON a.id = k.service_account_id
+    WHERE k.key_hash = $1
+      AND k.revoked_at IS NULL
+      AND (k.expires_at IS NULL OR k.expires_at > $2)
+      AND a.is_active = true
+"#;
+
+/// Revoke an API key
+pub const REVOKE_API_KEY: &str = r#"
+    UPDATE api_keys
+    SET revoked_at = $3
+    WHERE id = $1 AND service_account_id = $2 AND revoked_at IS NULL
+"#;
+
+/// Revoke every API key of a service account
+pub const REVOKE_ACCOUNT_API_KEYS: &str = r#"
+    UPDATE api_keys
+    SET revoked_at = $2
+    WHERE service_account_id = $1 AND revoked_at IS NULL
+"#;
+
+/// Shorten the lifetime of a key being rotated out
+pub const EXPIRE_API_KEY: &str = r#"
+    UPDATE api_keys
+    SET expires_at = $2
+    WHERE id = $1 AND (expires_at IS NULL OR expires_at > $2)
+"#;
+
+/// Record the last use of an API key
+pub const TOUCH_API_KEY: &str = r#"
+    UPDATE api_keys
+    SET last_used_at = $2, last_used_ip = $3::inet
+    WHERE id = $1
+"#;
+
+/// Insert an API key usage record
+pub const INSERT_API_KEY_USAGE: &str = r#"
+    INSERT INTO api_key_usage (id, api_key_id, service_account_id, method, endpoint, status_code, ip_address, used_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7::inet, $8)
+"#;
+
+/// Recent usage of a service account
+pub const GET_ACCOUNT_USAGE: &str = r#"
+    SELECT id, api_key_id, method, endpoint, status_code, host(ip_address) AS ip_address, used_at
+    FROM api_key_usage
+    WHERE service_account_id = $1 AND used_at >= $2
+    ORDER BY used_at DESC
+    LIMIT $3
+"#;
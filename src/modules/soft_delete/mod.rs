@@ -0,0 +1,52 @@
+//! Soft Delete Module
+//!
+//! This module gives clinical resources one deletion convention including:
+//! - `deleted_at`/`deleted_by` on each resource, excluded from reads and searches
+//! - `GET /{type}/_deleted` and `POST /{type}/{id}/restore` behind the restore-data permission
+//! - Hard purge of resources and their history once `DELETED_RECORD_RETENTION_DAYS` has passed
+//! - Audit entries for every restore, by the user, and every purge, by the system
+
+#[path = "soft_delete.controller.rs"]
+pub mod soft_delete_controller;
+#[path = "soft_delete.service.rs"]
+pub mod soft_delete_service;
+#[path = "soft_delete.sql.rs"]
+pub mod soft_delete_sql;
+
+pub use soft_delete_controller::SoftDeleteController;
+pub use soft_delete_service::{
+    DeletedResource, PurgeReport, RestoredResource, SoftDeletable, SoftDeleteConfig, SoftDeleteService,
+    SOFT_DELETABLE,
+};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Soft Delete Module Configuration
+pub struct SoftDeleteModule {
+    pub service: Arc<SoftDeleteService>,
+    pub controller: Arc<SoftDeleteController>,
+}
+
+impl SoftDeleteModule {
+    /// Create a new Soft Delete Module with dependency injection
+    pub fn new(db_pool: PgPool, config: SoftDeleteConfig) -> Self {
+        let service = Arc::new(SoftDeleteService::new(db_pool, config));
+        let controller = Arc::new(SoftDeleteController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register restore routes for a FHIR resource type, to be merged into
+    /// that resource's router
+    pub fn routes(&self, resource_type: &'static str) -> ApiRouter {
+        self.controller.routes(resource_type)
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<SoftDeleteService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::soft_delete::soft_delete_service::{DeletedResource, RestoredResource};
+use crate::modules::soft_delete::SoftDeleteService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Lists and restores deleted resources of one resource type
+pub struct SoftDeleteController {
+    soft_delete_service: Arc<SoftDeleteService>,
+}
+
+#[derive(Clone)]
+pub struct SoftDeleteState {
+    soft_delete_service: Arc<SoftDeleteService>,
+    /// FHIR type of the resources under this router
+    resource_type: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeletedQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl SoftDeleteController {
+    /// Create new controller with injected service
+    pub fn new(soft_delete_service: Arc<SoftDeleteService>) -> Self {
+        Self { soft_delete_service }
+    }
+
+    /// Restore routes for one resource type, to be merged into that
+    /// resource's router
+    pub fn routes(&self, resource_type: &'static str) -> ApiRouter {
+        ApiRouter::new()
+            .get("/_deleted", Self::list_deleted, "Deleted resources awaiting restore or purge, newest first")
+            .post("/:id/restore", Self::restore, "Restore a deleted resource as a new version")
+            .with_state(SoftDeleteState {
+                soft_delete_service: self.soft_delete_service.clone(),
+                resource_type,
+            })
+    }
+
+    /// Deleted resources of the router's type, most recently deleted first
+    pub async fn list_deleted(
+        State(state): State<SoftDeleteState>,
+        headers: HeaderMap,
+        Query(params): Query<DeletedQuery>,
+    ) -> Result<Json<Vec<DeletedResource>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        state
+            .soft_delete_service
+            .list_deleted(actor, state.resource_type, params.limit.unwrap_or(50), params.offset.unwrap_or(0))
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list deleted resources", e))
+    }
+
+    /// Undo a resource's deletion
+    pub async fn restore(
+        State(state): State<SoftDeleteState>,
+        auth: AuthContext,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<RestoredResource>, ErrorReply> {
+        tracing::info!("Restoring {}/{}", state.resource_type, id);
+
+        match state.soft_delete_service.restore(&auth, state.resource_type, id).await {
+            Ok(Some(restored)) => Ok(Json(restored)),
+            Ok(None) => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Not found".to_string(),
+                    message: format!("No deleted {} with id {}", state.resource_type, id),
+                }),
+            )),
+            Err(e) => Err(Self::error_response("Failed to restore resource", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
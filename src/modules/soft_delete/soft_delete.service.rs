@@ -0,0 +1,433 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::models::constants::AUDIT_LOG_RETENTION_DAYS;
+use crate::models::{AuditAction, AuditEventType, AuditLog, AuditResourceType};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::Action;
+use crate::modules::role::RoleService;
+use crate::modules::scheduler::ScheduledJob;
+use crate::modules::soft_delete::soft_delete_sql;
+
+/// A FHIR resource type stored with `deleted_at`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoftDeletable {
+    pub resource_type: &'static str,
+    pub table: &'static str,
+    /// Column naming the patient the row belongs to
+    pub patient_column: &'static str,
+    /// Further assignments undoing the deletion
+    restore: &'static str,
+}
+
+/// Soft-deleted resources, ordered so that rows are purged before the rows
+/// they reference
+pub const SOFT_DELETABLE: [SoftDeletable; 8] = [
+    SoftDeletable {
+        resource_type: "Task",
+        table: "tasks",
+        patient_column: "patient_id",
+        restore: "",
+    },
+    SoftDeletable {
+        resource_type: "CarePlan",
+        table: "care_plans",
+        patient_column: "patient_id",
+        restore: "",
+    },
+    SoftDeletable {
+        resource_type: "ServiceRequest",
+        table: "referrals",
+        patient_column: "patient_id",
+        restore: "",
+    },
+    SoftDeletable {
+        resource_type: "Observation",
+        table: "vital_signs",
+        patient_column: "patient_id",
+        restore: "",
+    },
+    SoftDeletable {
+        resource_type: "Condition",
+        table: "conditions",
+        patient_column: "patient_id",
+        restore: "",
+    },
+    SoftDeletable {
+        resource_type: "DocumentReference",
+        table: "medical_records",
+        patient_column: "patient_id",
+        restore: "",
+    },
+    SoftDeletable {
+        resource_type: "Appointment",
+        table: "appointments",
+        patient_column: "patient_id",
+        restore: "",
+    },
+    // Deleting a patient also clears `active`, which the patient queries check
+    SoftDeletable {
+        resource_type: "Patient",
+        table: "patients",
+        patient_column: "id",
+        restore: ", active = true",
+    },
+];
+
+impl SoftDeletable {
+    pub fn find(resource_type: &str) -> Option<&'static SoftDeletable> {
+        SOFT_DELETABLE.iter().find(|resource| resource.resource_type == resource_type)
+    }
+
+    /// `template` for this resource's table
+    fn sql(&self, template: &str) -> String {
+        template
+            .replace("{table}", self.table)
+            .replace("{patient}", self.patient_column)
+            .replace("{restore}", self.restore)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SoftDeleteConfig {
+    /// Days a deleted row is kept before it and its history are purged;
+    /// 0 keeps deleted rows
+    pub retention_days: u32,
+}
+
+impl Default for SoftDeleteConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: AUDIT_LOG_RETENTION_DAYS,
+        }
+    }
+}
+
+impl SoftDeleteConfig {
+    pub fn from_env() -> Self {
+        Self {
+            retention_days: std::env::var("DELETED_RECORD_RETENTION_DAYS")
+                .ok()
+                .and_then(|days| days.trim().parse().ok())
+                .unwrap_or_else(|| Self::default().retention_days),
+        }
+    }
+}
+
+/// A deleted resource awaiting restore or purge
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedResource {
+    pub resource_type: String,
+    pub id: Uuid,
+    pub patient_id: Option<Uuid>,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoredResource {
+    pub resource_type: String,
+    pub id: Uuid,
+    pub version_id: String,
+}
+
+/// What a retention purge removed
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeReport {
+    /// Rows deleted before this were purged; absent when retention is off
+    pub cutoff: Option<DateTime<Utc>>,
+    pub resources: Vec<PurgedResources>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgedResources {
+    pub resource_type: String,
+    pub purged: u64,
+    /// Rows past retention kept because other rows still reference them
+    pub kept: u64,
+}
+
+/// Soft delete service restoring deleted resources and purging them once
+/// retention has passed
+pub struct SoftDeleteService {
+    pool: PgPool,
+    config: SoftDeleteConfig,
+    role_service: RoleService,
+}
+
+impl SoftDeleteService {
+    /// Create new soft delete service
+    pub fn new(pool: PgPool, config: SoftDeleteConfig) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+            config,
+        }
+    }
+
+    /// Deleted resources of a type in the current tenant, most recently
+    /// deleted first
+    pub async fn list_deleted(
+        &self,
+        actor: Uuid,
+        resource_type: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DeletedResource>, HimsError> {
+        let resource = Self::resource(resource_type)?;
+        self.require(actor, Action::RestoreData).await?;
+
+        let rows = sqlx::query(&resource.sql(soft_delete_sql::LIST_DELETED))
+            .bind(limit.clamp(1, 100))
+            .bind(offset.max(0))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(DeletedResource {
+                    resource_type: resource.resource_type.to_string(),
+                    id: row.try_get("id")?,
+                    patient_id: row.try_get("patient_id")?,
+                    deleted_at: row.try_get("deleted_at")?,
+                    deleted_by: row.try_get("deleted_by")?,
+                })
+            })
+            .collect::<Result<_, sqlx::Error>>()
+            .map_err(database_error)
+    }
+
+    /// Undo a resource's deletion as a new version, audited as the user's
+    /// update of it. `None` when no deleted resource has the id. A
+    /// patient's resources wait for the patient to be restored first.
+    pub async fn restore(
+        &self,
+        auth: &AuthContext,
+        resource_type: &str,
+        id: Uuid,
+    ) -> Result<Option<RestoredResource>, HimsError> {
+        let resource = Self::resource(resource_type)?;
+        self.require(auth.user_id, Action::RestoreData).await?;
+
+        if resource.table != "patients" {
+            let patient_deleted: Option<bool> = sqlx::query_scalar(&resource.sql(soft_delete_sql::PATIENT_DELETED))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(database_error)?;
+            if patient_deleted == Some(true) {
+                return Err(HimsError::ConflictError {
+                    message: format!(
+                        "{}/{} belongs to a deleted patient; restore the patient first",
+                        resource_type, id
+                    ),
+                });
+            }
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let row = sqlx::query(&resource.sql(soft_delete_sql::RESTORE))
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError {
+                    message: format!("{}/{} conflicts with a resource created since it was deleted", resource_type, id),
+                },
+                _ => database_error(e),
+            })?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let restored = RestoredResource {
+            resource_type: resource.resource_type.to_string(),
+            id: row.try_get("id").map_err(database_error)?,
+            version_id: row.try_get("version_id").map_err(database_error)?,
+        };
+        let patient_id: Uuid = row.try_get("patient_id").map_err(database_error)?;
+
+        let audit_log = auth
+            .audit(AuditLog::new(AuditEventType::Update, AuditAction::Update, resource_type.to_string()))
+            .with_patient(patient_id)
+            .with_resource(restored.id)
+            .with_details(format!("Restored {}/{} as version {}", resource_type, id, restored.version_id));
+        AuditService::create_audit_log_in(&mut tx, &audit_log).await?;
+        tx.commit().await.map_err(database_error)?;
+
+        tracing::info!("User {} restored {}/{}", auth.user_id, resource_type, id);
+        Ok(Some(restored))
+    }
+
+    /// Hard-delete resources deleted longer ago than retention, with their
+    /// history, across every tenant. Each purged resource is audited as
+    /// deleted by the system.
+    pub async fn purge_expired(&self) -> Result<PurgeReport, HimsError> {
+        if self.config.retention_days == 0 {
+            return Ok(PurgeReport {
+                cutoff: None,
+                resources: Vec::new(),
+            });
+        }
+        let cutoff = Utc::now() - Duration::days(self.config.retention_days.into());
+
+        let resources = with_tenant(TenantContext::system(), async {
+            let mut resources = Vec::with_capacity(SOFT_DELETABLE.len());
+            for resource in &SOFT_DELETABLE {
+                resources.push(self.purge(resource, cutoff).await?);
+            }
+            Ok::<_, HimsError>(resources)
+        })
+        .await?;
+
+        for purged in resources.iter().filter(|purged| purged.purged > 0 || purged.kept > 0) {
+            tracing::info!(
+                "Purged {} deleted {} resources; kept {} still referenced",
+                purged.purged,
+                purged.resource_type,
+                purged.kept
+            );
+        }
+        Ok(PurgeReport {
+            cutoff: Some(cutoff),
+            resources,
+        })
+    }
+
+    /// Purge one table in a single statement, falling back to row by row
+    /// when some rows are still referenced
+    async fn purge(&self, resource: &SoftDeletable, cutoff: DateTime<Utc>) -> Result<PurgedResources, HimsError> {
+        let mut report = PurgedResources {
+            resource_type: resource.resource_type.to_string(),
+            purged: 0,
+            kept: 0,
+        };
+
+        match self.purge_rows(resource, &resource.sql(soft_delete_sql::PURGE_EXPIRED), None, cutoff).await {
+            Ok(purged) => {
+                report.purged = purged;
+                return Ok(report);
+            }
+            Err(sqlx::Error::Database(db)) if db.is_foreign_key_violation() => {}
+            Err(e) => return Err(database_error(e)),
+        }
+
+        let ids: Vec<Uuid> = sqlx::query_scalar(&resource.sql(soft_delete_sql::LIST_EXPIRED))
+            .bind(cutoff)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let purge_one = resource.sql(soft_delete_sql::PURGE_ONE);
+        for id in ids {
+            match self.purge_rows(resource, &purge_one, Some(id), cutoff).await {
+                Ok(purged) => report.purged += purged,
+                Err(sqlx::Error::Database(db)) if db.is_foreign_key_violation() => report.kept += 1,
+                Err(e) => return Err(database_error(e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Run a purge statement and delete the purged rows' history with it,
+    /// auditing each purged row in the same transaction
+    async fn purge_rows(
+        &self,
+        resource: &SoftDeletable,
+        statement: &str,
+        id: Option<Uuid>,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(soft_delete_sql::ALLOW_HISTORY_PURGE).execute(&mut *tx).await?;
+
+        let query = match id {
+            Some(id) => sqlx::query(statement).bind(id),
+            None => sqlx::query(statement),
+        };
+        let rows = query.bind(cutoff).fetch_all(&mut *tx).await?;
+        let mut purged = Vec::with_capacity(rows.len());
+        let mut patients = Vec::with_capacity(rows.len());
+        let mut tenants = Vec::with_capacity(rows.len());
+        for row in &rows {
+            purged.push(row.try_get::<Uuid, _>("id")?);
+            // A purged patient's row is gone, so its entry cannot reference it
+            patients.push(Some(row.try_get::<Uuid, _>("patient_id")?).filter(|_| resource.table != "patients"));
+            tenants.push(row.try_get::<Uuid, _>("tenant_id")?);
+        }
+        if !purged.is_empty() {
+            sqlx::query(soft_delete_sql::PURGE_HISTORY)
+                .bind(resource.resource_type)
+                .bind(&purged)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(soft_delete_sql::AUDIT_PURGED)
+                .bind(AuditEventType::Delete.to_string())
+                .bind(AuditAction::Delete.to_string())
+                .bind(AuditResourceType::from_string(resource.resource_type).to_string())
+                .bind(format!("Purged deleted {} past retention, before {}", resource.resource_type, cutoff))
+                .bind(&purged)
+                .bind(&patients)
+                .bind(&tenants)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(purged.len() as u64)
+    }
+
+    fn resource(resource_type: &str) -> Result<&'static SoftDeletable, HimsError> {
+        SoftDeletable::find(resource_type).ok_or_else(|| HimsError::ValidationError {
+            message: format!("{} resources cannot be restored", resource_type),
+        })
+    }
+
+    /// Require a permission of the actor
+    async fn require(&self, actor: Uuid, action: Action) -> Result<(), HimsError> {
+        if !self.role_service.get_user_permissions(actor).await?.contains(&action) {
+            tracing::warn!("User {} lacks {} permission", actor, action);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", action),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+#[async_trait]
+impl ScheduledJob for SoftDeleteService {
+    async fn run(&self) -> Result<Value, HimsError> {
+        let report = self.purge_expired().await?;
+        let purged: u64 = report.resources.iter().map(|resource| resource.purged).sum();
+        let kept: u64 = report.resources.iter().map(|resource| resource.kept).sum();
+        Ok(json!({ "purged": purged, "kept": kept }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resources() {
+        assert_eq!(SoftDeletable::find("Observation").unwrap().table, "vital_signs");
+        assert!(SoftDeletable::find("Organization").is_none());
+        // Patients are referenced by every other resource, so go last
+        assert_eq!(SOFT_DELETABLE.last().unwrap().resource_type, "Patient");
+
+        let patient = SoftDeletable::find("Patient").unwrap();
+        assert_eq!(
+            patient.sql("UPDATE {table} SET deleted_at = NULL{restore} WHERE {patient} = $1"),
+            "UPDATE patients SET deleted_at = NULL, active = true WHERE id = $1"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Soft Delete SQL Queries
+//!
+//! This file contains all SQL queries used by the soft delete service.
+//! `{table}` and `{patient}` stand for a resource's table and patient
+//! column, filled in from the service's fixed list of resources.
+
+/// Deleted rows of a table, most recently deleted first
+pub const LIST_DELETED: &str = r#"
+    SELECT id, {patient} AS patient_id, deleted_at, deleted_by
+    FROM {table}
+    WHERE deleted_at IS NOT NULL
+    ORDER BY deleted_at DESC, id
+    LIMIT $1 OFFSET $2
+"#;
+
+/// Whether the patient of row $1 is itself deleted
+pub const PATIENT_DELETED: &str = r#"
+    SELECT p.deleted_at IS NOT NULL
+    FROM {table} r
+    JOIN patients p ON p.id = r.{patient}
+    WHERE r.id = $1
+"#;
+
+/// Clear row $1's deletion as a new version. `{restore}` holds any further
+/// columns the resource's deletion changed.
+pub const RESTORE: &str = r#"
+    UPDATE {table}
+    SET deleted_at = NULL{restore}, meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NOT NULL
+    RETURNING id, {patient} AS patient_id, COALESCE(meta->>'version_id', '1') AS version_id
+"#;
+
+/// Let this transaction delete resource history
+pub const ALLOW_HISTORY_PURGE: &str = r#"
+    SELECT set_config('app.retention_purge', 'on', true)
+"#;
+
+/// Hard-delete rows deleted before $1
+pub const PURGE_EXPIRED: &str = r#"
+    DELETE FROM {table} WHERE deleted_at < $1 RETURNING id, {patient} AS patient_id, tenant_id
+"#;
+
+pub const LIST_EXPIRED: &str = r#"
+    SELECT id FROM {table} WHERE deleted_at < $1 ORDER BY deleted_at
+"#;
+
+pub const PURGE_ONE: &str = r#"
+    DELETE FROM {table} WHERE id = $1 AND deleted_at < $2 RETURNING id, {patient} AS patient_id, tenant_id
+"#;
+
+/// History of purged resources $2 of type $1
+pub const PURGE_HISTORY: &str = r#"
+    DELETE FROM resource_history WHERE resource_type = $1 AND resource_id = ANY($2)
+"#;
+
+/// Audit the purge of resources $5 of patients $6 in tenants $7 as event
+/// $1 and action $2 on resource type $3 with details $4. The purge runs as
+/// the system, so no user is recorded; each entry keeps the tenant of the
+/// row it records.
+pub const AUDIT_PURGED: &str = r#"
+    INSERT INTO audit_logs (event_type, action, resource_type, details, resource_id, patient_id, tenant_id, outcome)
+    SELECT $1, $2, $3, $4, purged.id, purged.patient_id, purged.tenant_id, 'success'
+    FROM unnest($5::uuid[], $6::uuid[], $7::uuid[]) AS purged (id, patient_id, tenant_id)
+"#;
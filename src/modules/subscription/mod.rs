@@ -0,0 +1,54 @@
+//! Subscription Module
+//!
+//! This module provides FHIR R4 Subscriptions (with the subscriptions backport) including:
+//! - Subscription CRUD and `$status`, with criteria checked against the supported search parameters
+//! - Matching of new resource versions from the resource history against active criteria
+//! - rest-hook delivery with exponential backoff, erroring the subscription after the last attempt
+//! - A websocket channel that bound clients, such as the desktop app, receive notifications on
+
+#[path = "subscription.controller.rs"]
+pub mod subscription_controller;
+#[path = "subscription.service.rs"]
+pub mod subscription_service;
+#[path = "subscription.sql.rs"]
+pub mod subscription_sql;
+
+pub use subscription_controller::SubscriptionController;
+pub use subscription_service::{
+    ChannelType, PayloadContent, Subscription, SubscriptionCriteria, SubscriptionHub, SubscriptionService,
+    SubscriptionStatus,
+};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Subscription Module Configuration
+pub struct SubscriptionModule {
+    pub service: Arc<SubscriptionService>,
+    pub controller: Arc<SubscriptionController>,
+}
+
+impl SubscriptionModule {
+    /// Create a new Subscription Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(SubscriptionService::new(db_pool));
+        let controller = Arc::new(SubscriptionController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register subscription routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<SubscriptionService> {
+        self.service.clone()
+    }
+}
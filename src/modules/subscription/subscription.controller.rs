@@ -0,0 +1,261 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{current_tenant, with_tenant};
+use crate::modules::subscription::subscription_service::Subscription;
+use crate::modules::subscription::SubscriptionService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Subscription controller for FHIR Subscription management and the
+/// websocket notification channel
+pub struct SubscriptionController {
+    subscription_service: Arc<SubscriptionService>,
+}
+
+impl SubscriptionController {
+    /// Create new controller with injected service
+    pub fn new(subscription_service: Arc<SubscriptionService>) -> Self {
+        Self { subscription_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_subscription, "Create subscription")
+            .get("/", Self::list_subscriptions, "List subscriptions")
+            .get("/websocket", Self::websocket, "Receive websocket channel notifications")
+            .get("/:id", Self::get_subscription, "Get subscription by ID")
+            .put("/:id", Self::update_subscription, "Update subscription")
+            .delete("/:id", Self::delete_subscription, "Delete subscription")
+            .get("/:id/$status", Self::get_status, "Get subscription status")
+            .with_state(self.subscription_service.clone())
+    }
+
+    /// Create new subscription
+    pub async fn create_subscription(
+        State(subscription_service): State<Arc<SubscriptionService>>,
+        headers: HeaderMap,
+        Json(payload): Json<Subscription>,
+    ) -> Result<(StatusCode, Json<Subscription>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Creating subscription for {}", payload.criteria);
+
+        match subscription_service.create_subscription(actor, payload).await {
+            Ok(subscription) => Ok((StatusCode::CREATED, Json(subscription))),
+            Err(e) => Err(Self::error_response("Failed to create subscription", e)),
+        }
+    }
+
+    /// List subscriptions
+    pub async fn list_subscriptions(
+        State(subscription_service): State<Arc<SubscriptionService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<Subscription>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match subscription_service.list_subscriptions().await {
+            Ok(subscriptions) => Ok(Json(subscriptions)),
+            Err(e) => Err(Self::error_response("Failed to list subscriptions", e)),
+        }
+    }
+
+    /// Get subscription by ID
+    pub async fn get_subscription(
+        State(subscription_service): State<Arc<SubscriptionService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Subscription>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match subscription_service.get_subscription(id).await {
+            Ok(Some(subscription)) => Ok(Json(subscription)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve subscription", e)),
+        }
+    }
+
+    /// Update subscription
+    pub async fn update_subscription(
+        State(subscription_service): State<Arc<SubscriptionService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<Subscription>,
+    ) -> Result<Json<Subscription>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Updating subscription: {}", id);
+
+        match subscription_service.update_subscription(id, payload).await {
+            Ok(Some(subscription)) => Ok(Json(subscription)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update subscription", e)),
+        }
+    }
+
+    /// Delete subscription, discarding its undelivered notifications
+    pub async fn delete_subscription(
+        State(subscription_service): State<Arc<SubscriptionService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Deleting subscription: {}", id);
+
+        match subscription_service.delete_subscription(id).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to delete subscription", e)),
+        }
+    }
+
+    /// `$status` operation: the subscription's state and event count
+    pub async fn get_status(
+        State(subscription_service): State<Arc<SubscriptionService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Value>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match subscription_service.status(id).await {
+            Ok(Some(status)) => Ok(Json(status)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve subscription status", e)),
+        }
+    }
+
+    /// Websocket channel. The client sends `bind <subscription id>` for each
+    /// websocket subscription it wants, is answered `bound <id>` or
+    /// `error <id> <reason>`, and then receives notification bundles as
+    /// JSON text messages.
+    pub async fn websocket(
+        State(subscription_service): State<Arc<SubscriptionService>>,
+        headers: HeaderMap,
+        ws: WebSocketUpgrade,
+    ) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        // The socket outlives the request, so it carries the tenant itself
+        let Some(tenant) = current_tenant() else {
+            return Err(Self::error_response(
+                "Failed to open subscription websocket",
+                HimsError::SecurityError { message: "No tenant resolved for the request".to_string() },
+            ));
+        };
+
+        Ok(ws.on_upgrade(move |socket| with_tenant(tenant, Self::serve_socket(subscription_service, socket))))
+    }
+
+    async fn serve_socket(subscription_service: Arc<SubscriptionService>, mut socket: WebSocket) {
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+
+        loop {
+            tokio::select! {
+                message = socket.recv() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                        Some(Ok(_)) => continue,
+                    };
+                    let reply = Self::bind(&subscription_service, text.trim(), &sender).await;
+                    if socket.send(Message::Text(reply)).await.is_err() {
+                        break;
+                    }
+                }
+                Some(bundle) = notifications.recv() => {
+                    if socket.send(Message::Text(bundle)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn bind(
+        subscription_service: &SubscriptionService,
+        command: &str,
+        sender: &mpsc::UnboundedSender<String>,
+    ) -> String {
+        let Some(argument) = command.strip_prefix("bind ") else {
+            return format!("error - unknown command {:?}", command);
+        };
+        let Ok(id) = Uuid::parse_str(argument.trim()) else {
+            return format!("error {} not a subscription id", argument.trim());
+        };
+
+        match subscription_service.websocket_subscription(id).await {
+            Ok(Some(_)) => {
+                subscription_service.hub().bind(id, sender.clone());
+                format!("bound {}", id)
+            }
+            Ok(None) => format!("error {} no websocket subscription with this id", id),
+            Err(e) => {
+                tracing::error!("Failed to bind websocket to subscription {}: {}", id, e);
+                format!("error {} lookup failed", id)
+            }
+        }
+    }
+
+    /// Get authenticated user from headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(id: Uuid) -> (StatusCode, Json<ErrorResponse>) {
+        tracing::warn!("Subscription not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Subscription not found".to_string(),
+                message: format!("Subscription with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        if status == StatusCode::FORBIDDEN {
+            tracing::warn!("{}: {}", context, e);
+        } else {
+            tracing::error!("{}: {}", context, e);
+        }
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
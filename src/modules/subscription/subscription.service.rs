@@ -0,0 +1,845 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::modules::appointment::AppointmentSearch;
+use crate::modules::patient::PatientSearch;
+
+// Import SQL queries from separate file
+use crate::modules::subscription::subscription_sql::*;
+
+/// Profile of the status Parameters heading each notification bundle
+pub const SUBSCRIPTION_STATUS_PROFILE: &str =
+    "http://hl7.org/fhir/uv/subscriptions-backport/StructureDefinition/backport-subscription-status-r4";
+
+/// Extension on `channel.payload` choosing how much of the resource is sent
+pub const PAYLOAD_CONTENT_EXTENSION: &str =
+    "http://hl7.org/fhir/uv/subscriptions-backport/StructureDefinition/backport-payload-content";
+
+/// Resource versions matched per dispatcher pass
+const DISPATCH_BATCH: i64 = 500;
+
+/// Notifications sent per delivery pass
+const DELIVERY_BATCH: i64 = 100;
+
+/// Attempts before a notification is given up and its subscription errored
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+
+/// Timeout for one rest-hook POST
+const REST_HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionStatus {
+    Requested,
+    Active,
+    Error,
+    Off,
+}
+
+impl SubscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::Requested => "requested",
+            SubscriptionStatus::Active => "active",
+            SubscriptionStatus::Error => "error",
+            SubscriptionStatus::Off => "off",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "requested" => SubscriptionStatus::Requested,
+            "active" => SubscriptionStatus::Active,
+            "error" => SubscriptionStatus::Error,
+            _ => SubscriptionStatus::Off,
+        }
+    }
+}
+
+/// How notifications reach the subscriber
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelType {
+    /// POST to the subscriber's endpoint, retried with backoff
+    RestHook,
+    /// Pushed over `GET /api/v1/subscriptions/websocket` to bound clients
+    Websocket,
+}
+
+impl ChannelType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelType::RestHook => "rest-hook",
+            ChannelType::Websocket => "websocket",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "websocket" => ChannelType::Websocket,
+            _ => ChannelType::RestHook,
+        }
+    }
+}
+
+/// How much of the resource a notification carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PayloadContent {
+    /// Only that something changed
+    Empty,
+    /// The reference to the changed version
+    IdOnly,
+    /// The resource as of the changed version
+    FullResource,
+}
+
+impl PayloadContent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadContent::Empty => "empty",
+            PayloadContent::IdOnly => "id-only",
+            PayloadContent::FullResource => "full-resource",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "empty" => PayloadContent::Empty,
+            "full-resource" => PayloadContent::FullResource,
+            _ => PayloadContent::IdOnly,
+        }
+    }
+}
+
+/// FHIR R4 Subscription, with the backport's payload content extension
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    #[serde(default = "Subscription::resource_type")]
+    pub resource_type: String,
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    pub status: SubscriptionStatus,
+    /// Search URL resources must match, e.g. `Appointment?status=booked`
+    pub criteria: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub end: Option<DateTime<Utc>>,
+    #[serde(default, skip_deserializing)]
+    pub error: Option<String>,
+    pub channel: SubscriptionChannel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionChannel {
+    #[serde(rename = "type")]
+    pub channel_type: ChannelType,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// MIME type of notifications; absent means empty notifications
+    #[serde(default)]
+    pub payload: Option<String>,
+    /// Extra HTTP headers for rest-hook requests, as `Name: value`
+    #[serde(default)]
+    pub header: Vec<String>,
+    #[serde(rename = "_payload", default, skip_serializing_if = "Option::is_none")]
+    pub payload_element: Option<Value>,
+}
+
+impl Subscription {
+    fn resource_type() -> String {
+        "Subscription".to_string()
+    }
+}
+
+impl SubscriptionChannel {
+    /// Content from the backport extension; without it an R4 payload means
+    /// the full resource, and no payload means an empty notification
+    pub fn content(&self) -> PayloadContent {
+        let extension = self
+            .payload_element
+            .as_ref()
+            .and_then(|element| element.get("extension"))
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find(|extension| extension.get("url").and_then(Value::as_str) == Some(PAYLOAD_CONTENT_EXTENSION))
+            .and_then(|extension| extension.get("valueCode"))
+            .and_then(Value::as_str);
+
+        match (extension, &self.payload) {
+            (Some(code), _) => PayloadContent::from_string(code),
+            (None, Some(_)) => PayloadContent::FullResource,
+            (None, None) => PayloadContent::Empty,
+        }
+    }
+
+    fn with_content(mut self, content: PayloadContent) -> Self {
+        self.payload_element = Some(json!({
+            "extension": [{ "url": PAYLOAD_CONTENT_EXTENSION, "valueCode": content.as_str() }]
+        }));
+        self
+    }
+}
+
+/// A parsed subscription criteria URL. Matching reuses the search
+/// parameters each resource supports.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionCriteria {
+    Patient(Box<PatientSearch>),
+    Appointment(Box<AppointmentSearch>),
+    /// Every document; record searches take no parameters yet
+    DocumentReference,
+}
+
+impl SubscriptionCriteria {
+    pub fn parse(criteria: &str) -> Result<Self, HimsError> {
+        let invalid = |message: String| HimsError::ValidationError { message };
+        let (resource_type, query) = criteria.split_once('?').unwrap_or((criteria, ""));
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(query)
+            .map_err(|e| invalid(format!("Invalid criteria {:?}: {}", criteria, e)))?;
+
+        // Result parameters shape a page of results, which has no meaning for a
+        // single changed resource
+        if let Some((name, _)) = params.iter().find(|(name, _)| name.starts_with('_') && name != "_id") {
+            return Err(invalid(format!("{} cannot be used in subscription criteria", name)));
+        }
+
+        match resource_type {
+            "Patient" => Ok(SubscriptionCriteria::Patient(Box::new(PatientSearch::from_params(&params)?))),
+            "Appointment" => Ok(SubscriptionCriteria::Appointment(Box::new(AppointmentSearch::from_params(&params)?))),
+            "DocumentReference" if params.is_empty() => Ok(SubscriptionCriteria::DocumentReference),
+            "DocumentReference" => Err(invalid("DocumentReference criteria take no search parameters".to_string())),
+            other => Err(invalid(format!("Subscriptions are not supported for {:?}", other))),
+        }
+    }
+
+    pub fn resource_type(&self) -> &'static str {
+        match self {
+            SubscriptionCriteria::Patient(_) => "Patient",
+            SubscriptionCriteria::Appointment(_) => "Appointment",
+            SubscriptionCriteria::DocumentReference => "DocumentReference",
+        }
+    }
+
+    /// Query whether resource `id` currently matches. `None` when any
+    /// resource of the type matches.
+    fn match_query(&self, id: Uuid) -> Option<QueryBuilder<'_, Postgres>> {
+        match self {
+            SubscriptionCriteria::Patient(search) => {
+                let mut query = QueryBuilder::new("SELECT EXISTS (SELECT 1 FROM patients");
+                search.push_filters(&mut query);
+                query.push(" AND id = ").push_bind(id).push(")");
+                Some(query)
+            }
+            SubscriptionCriteria::Appointment(search) => {
+                let mut query = QueryBuilder::new("SELECT EXISTS (SELECT 1 FROM appointments WHERE id = ");
+                query.push_bind(id);
+                search.push_filters(&mut query);
+                query.push(")");
+                Some(query)
+            }
+            SubscriptionCriteria::DocumentReference => None,
+        }
+    }
+}
+
+/// Websocket clients bound to subscriptions. Each bound socket gets its own
+/// queue of notification bundles.
+#[derive(Debug, Default)]
+pub struct SubscriptionHub {
+    bound: RwLock<HashMap<Uuid, Vec<mpsc::UnboundedSender<String>>>>,
+}
+
+impl SubscriptionHub {
+    pub fn bind(&self, subscription_id: Uuid, sender: mpsc::UnboundedSender<String>) {
+        self.bound
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(subscription_id)
+            .or_default()
+            .push(sender);
+    }
+
+    /// Send to every socket bound to the subscription, forgetting closed
+    /// ones. Returns how many received it.
+    pub fn publish(&self, subscription_id: Uuid, message: &str) -> usize {
+        let mut bound = self.bound.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(senders) = bound.get_mut(&subscription_id) else {
+            return 0;
+        };
+        senders.retain(|sender| sender.send(message.to_string()).is_ok());
+        let delivered = senders.len();
+        if delivered == 0 {
+            bound.remove(&subscription_id);
+        }
+        delivered
+    }
+}
+
+/// A queued notification with what is needed to send it
+#[derive(Debug, Clone)]
+pub struct PendingNotification {
+    pub id: i64,
+    pub subscription_id: Uuid,
+    pub event_number: i64,
+    pub attempts: i32,
+    pub channel_type: ChannelType,
+    pub endpoint: Option<String>,
+    pub headers: Vec<String>,
+    pub content: PayloadContent,
+    pub events_since_start: i64,
+    pub resource_type: String,
+    pub resource_id: Uuid,
+    pub version_id: String,
+    pub operation: String,
+    pub resource: Value,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl PendingNotification {
+    /// R4 backport notification: a history bundle headed by the
+    /// subscription's status
+    pub fn bundle(&self) -> Value {
+        let reference = format!("{}/{}", self.resource_type, self.resource_id);
+        let mut event = vec![
+            json!({ "name": "event-number", "valueString": self.event_number.to_string() }),
+            json!({ "name": "timestamp", "valueInstant": self.changed_at }),
+        ];
+        if self.content != PayloadContent::Empty {
+            event.push(json!({
+                "name": "focus",
+                "valueReference": { "reference": format!("{}/_history/{}", reference, self.version_id) }
+            }));
+        }
+
+        let mut entry = vec![json!({
+            "fullUrl": format!("urn:uuid:{}", Uuid::new_v4()),
+            "resource": {
+                "resourceType": "Parameters",
+                "meta": { "profile": [SUBSCRIPTION_STATUS_PROFILE] },
+                "parameter": [
+                    { "name": "subscription", "valueReference": { "reference": format!("Subscription/{}", self.subscription_id) } },
+                    { "name": "status", "valueCode": SubscriptionStatus::Active.as_str() },
+                    { "name": "type", "valueCode": "event-notification" },
+                    { "name": "events-since-subscription-start", "valueString": self.events_since_start.to_string() },
+                    { "name": "notification-event", "part": event }
+                ]
+            },
+            "request": { "method": "GET", "url": format!("Subscription/{}/$status", self.subscription_id) },
+            "response": { "status": "200" }
+        })];
+
+        if self.content == PayloadContent::FullResource {
+            let mut resource = self.resource.clone();
+            if let Value::Object(fields) = &mut resource {
+                fields.insert("resourceType".to_string(), Value::String(self.resource_type.clone()));
+            }
+            let (method, url, status) = match self.operation.as_str() {
+                "create" => ("POST", self.resource_type.clone(), "201"),
+                _ => ("PUT", reference.clone(), "200"),
+            };
+            entry.push(json!({
+                "fullUrl": reference,
+                "resource": resource,
+                "request": { "method": method, "url": url },
+                "response": { "status": status }
+            }));
+        }
+
+        json!({
+            "resourceType": "Bundle",
+            "id": Uuid::new_v4(),
+            "type": "history",
+            "timestamp": Utc::now(),
+            "entry": entry
+        })
+    }
+}
+
+/// Wait before retry `attempt` (1-based): 30s doubling, capped at an hour
+pub fn retry_delay(attempt: i32) -> Duration {
+    let seconds = 30u64.saturating_mul(1 << attempt.clamp(1, 16).saturating_sub(1));
+    Duration::from_secs(seconds.min(3600))
+}
+
+/// Manages subscriptions, matches resource changes against them and
+/// delivers the resulting notifications
+pub struct SubscriptionService {
+    pool: PgPool,
+    hub: Arc<SubscriptionHub>,
+    http_client: reqwest::Client,
+}
+
+impl SubscriptionService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            hub: Arc::new(SubscriptionHub::default()),
+            http_client: reqwest::Client::builder()
+                .timeout(REST_HOOK_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn hub(&self) -> Arc<SubscriptionHub> {
+        self.hub.clone()
+    }
+
+    /// Register a subscription. Its criteria and channel are checked first.
+    pub async fn create_subscription(&self, actor: Uuid, subscription: Subscription) -> Result<Subscription, HimsError> {
+        let criteria = Self::validate(&subscription)?;
+
+        let row = sqlx::query(INSERT_SUBSCRIPTION)
+            .bind(Uuid::new_v4())
+            .bind(subscription.status.as_str())
+            .bind(&subscription.criteria)
+            .bind(criteria.resource_type())
+            .bind(&subscription.reason)
+            .bind(subscription.channel.channel_type.as_str())
+            .bind(&subscription.channel.endpoint)
+            .bind(json!(subscription.channel.header))
+            .bind(subscription.channel.content().as_str())
+            .bind(subscription.end)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Self::row_to_subscription(&row)
+    }
+
+    pub async fn get_subscription(&self, id: Uuid) -> Result<Option<Subscription>, HimsError> {
+        sqlx::query(GET_SUBSCRIPTION)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_subscription)
+            .transpose()
+    }
+
+    pub async fn list_subscriptions(&self) -> Result<Vec<Subscription>, HimsError> {
+        sqlx::query(LIST_SUBSCRIPTIONS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .iter()
+            .map(Self::row_to_subscription)
+            .collect()
+    }
+
+    /// Replace a subscription. Setting it `active` again after an error
+    /// resumes delivery of its queued notifications.
+    pub async fn update_subscription(&self, id: Uuid, subscription: Subscription) -> Result<Option<Subscription>, HimsError> {
+        let criteria = Self::validate(&subscription)?;
+
+        sqlx::query(UPDATE_SUBSCRIPTION)
+            .bind(id)
+            .bind(subscription.status.as_str())
+            .bind(&subscription.criteria)
+            .bind(criteria.resource_type())
+            .bind(&subscription.reason)
+            .bind(subscription.channel.channel_type.as_str())
+            .bind(&subscription.channel.endpoint)
+            .bind(json!(subscription.channel.header))
+            .bind(subscription.channel.content().as_str())
+            .bind(subscription.end)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_subscription)
+            .transpose()
+    }
+
+    pub async fn delete_subscription(&self, id: Uuid) -> Result<bool, HimsError> {
+        let result = sqlx::query(DELETE_SUBSCRIPTION)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// `$status` of a subscription as backport status Parameters
+    pub async fn status(&self, id: Uuid) -> Result<Option<Value>, HimsError> {
+        let Some(row) = sqlx::query(GET_SUBSCRIPTION)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+
+        let mut parameter = vec![
+            json!({ "name": "subscription", "valueReference": { "reference": format!("Subscription/{}", id) } }),
+            json!({ "name": "status", "valueCode": row.get::<String, _>("status") }),
+            json!({ "name": "type", "valueCode": "query-status" }),
+            json!({ "name": "events-since-subscription-start", "valueString": row.get::<i64, _>("events_since_start").to_string() }),
+        ];
+        if let Some(error) = row.get::<Option<String>, _>("error") {
+            parameter.push(json!({ "name": "error", "valueCodeableConcept": { "text": error } }));
+        }
+        Ok(Some(json!({
+            "resourceType": "Parameters",
+            "meta": { "profile": [SUBSCRIPTION_STATUS_PROFILE] },
+            "parameter": parameter
+        })))
+    }
+
+    /// Check criteria and channel settings, returning the parsed criteria
+    pub fn validate(subscription: &Subscription) -> Result<SubscriptionCriteria, HimsError> {
+        let invalid = |message: &str| HimsError::ValidationError { message: message.to_string() };
+        let criteria = SubscriptionCriteria::parse(&subscription.criteria)?;
+
+        if matches!(subscription.status, SubscriptionStatus::Error) {
+            return Err(invalid("Subscriptions cannot be set to error by clients"));
+        }
+        let channel = &subscription.channel;
+        if channel.channel_type == ChannelType::RestHook {
+            let endpoint = channel.endpoint.as_deref().unwrap_or_default();
+            if !(endpoint.starts_with("https://") || endpoint.starts_with("http://")) {
+                return Err(invalid("rest-hook subscriptions need an http(s) endpoint"));
+            }
+        }
+        if channel.header.iter().any(|header| !header.contains(':')) {
+            return Err(invalid("Channel headers must be written as Name: value"));
+        }
+        if channel.payload.as_deref().is_some_and(|payload| payload != "application/fhir+json" && payload != "application/json") {
+            return Err(invalid("Only application/fhir+json payloads are supported"));
+        }
+        Ok(criteria)
+    }
+
+    /// Websocket subscription a client may bind to, in the current tenant
+    pub async fn websocket_subscription(&self, id: Uuid) -> Result<Option<Subscription>, HimsError> {
+        Ok(self
+            .get_subscription(id)
+            .await?
+            .filter(|subscription| subscription.channel.channel_type == ChannelType::Websocket))
+    }
+
+    /// Match one batch of new resource versions against active
+    /// subscriptions and queue notifications. Runs across tenants. Returns
+    /// how many versions were examined.
+    pub async fn dispatch_once(&self) -> Result<usize, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+
+        let last_history_id: i64 = sqlx::query_scalar(LOCK_DISPATCH_STATE)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db)?;
+        let versions = sqlx::query(GET_NEW_VERSIONS)
+            .bind(last_history_id)
+            .bind(DISPATCH_BATCH)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(db)?;
+        if versions.is_empty() {
+            return Ok(0);
+        }
+
+        sqlx::query(EXPIRE_SUBSCRIPTIONS).execute(&mut *tx).await.map_err(db)?;
+        let mut subscriptions: Vec<(Uuid, Uuid, SubscriptionCriteria)> = Vec::new();
+        for row in sqlx::query(GET_ACTIVE_SUBSCRIPTIONS).fetch_all(&mut *tx).await.map_err(db)? {
+            let id: Uuid = row.get("id");
+            // Criteria were checked when stored; one that no longer parses
+            // must not hold up everyone else's notifications
+            match SubscriptionCriteria::parse(&row.get::<String, _>("criteria")) {
+                Ok(criteria) => subscriptions.push((id, row.get("tenant_id"), criteria)),
+                Err(e) => tracing::warn!("Skipping subscription {} with invalid criteria: {}", id, e),
+            }
+        }
+
+        let mut last_seen = last_history_id;
+        for version in &versions {
+            let history_id: i64 = version.get("id");
+            let tenant_id: Uuid = version.get("tenant_id");
+            let resource_type: String = version.get("resource_type");
+            let resource_id: Uuid = version.get("resource_id");
+            last_seen = history_id;
+
+            for (subscription_id, subscription_tenant, criteria) in &subscriptions {
+                if *subscription_tenant != tenant_id || criteria.resource_type() != resource_type {
+                    continue;
+                }
+                // Matched against the resource as it is now, which for a
+                // quickly re-edited resource may be a later version
+                let matches = match criteria.match_query(resource_id) {
+                    Some(mut query) => query.build_query_scalar::<bool>().fetch_one(&mut *tx).await.map_err(db)?,
+                    None => true,
+                };
+                if matches {
+                    sqlx::query(INSERT_NOTIFICATION)
+                        .bind(subscription_id)
+                        .bind(history_id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                }
+            }
+        }
+
+        sqlx::query(SAVE_DISPATCH_STATE).bind(last_seen).execute(&mut *tx).await.map_err(db)?;
+        tx.commit().await.map_err(db)?;
+        Ok(versions.len())
+    }
+
+    /// Send one batch of due notifications. Failed sends are retried with
+    /// backoff; after the last attempt the subscription is set to error.
+    /// Returns how many were delivered.
+    pub async fn deliver_once(&self) -> Result<usize, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+
+        let due = sqlx::query(GET_DUE_NOTIFICATIONS)
+            .bind(DELIVERY_BATCH)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(db)?
+            .iter()
+            .map(Self::row_to_notification)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut delivered = 0;
+        for notification in due {
+            match self.send(&notification).await {
+                Ok(()) => {
+                    sqlx::query(MARK_NOTIFICATION_DELIVERED)
+                        .bind(notification.id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                    delivered += 1;
+                }
+                Err(error) if notification.attempts + 1 >= MAX_DELIVERY_ATTEMPTS => {
+                    tracing::warn!(
+                        "Giving up on notification {} for subscription {}: {}",
+                        notification.event_number,
+                        notification.subscription_id,
+                        error
+                    );
+                    sqlx::query(FAIL_NOTIFICATION)
+                        .bind(notification.id)
+                        .bind(&error)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                    sqlx::query(SET_SUBSCRIPTION_ERROR)
+                        .bind(notification.subscription_id)
+                        .bind(format!("Delivery failed {} times: {}", MAX_DELIVERY_ATTEMPTS, error))
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                }
+                Err(error) => {
+                    tracing::debug!("Notification to subscription {} failed: {}", notification.subscription_id, error);
+                    sqlx::query(RETRY_NOTIFICATION)
+                        .bind(notification.id)
+                        .bind(&error)
+                        .bind(retry_delay(notification.attempts + 1).as_secs_f64())
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                }
+            }
+        }
+
+        tx.commit().await.map_err(db)?;
+        Ok(delivered)
+    }
+
+    async fn send(&self, notification: &PendingNotification) -> Result<(), String> {
+        let bundle = notification.bundle();
+        match notification.channel_type {
+            ChannelType::RestHook => {
+                let endpoint = notification.endpoint.as_deref().ok_or("No endpoint")?;
+                let mut request = self
+                    .http_client
+                    .post(endpoint)
+                    .header("Content-Type", "application/fhir+json")
+                    .body(bundle.to_string());
+                for header in &notification.headers {
+                    if let Some((name, value)) = header.split_once(':') {
+                        request = request.header(name.trim(), value.trim());
+                    }
+                }
+                let response = request.send().await.map_err(|e| e.to_string())?;
+                if response.status().is_success() {
+                    Ok(())
+                } else {
+                    Err(format!("Endpoint answered {}", response.status()))
+                }
+            }
+            ChannelType::Websocket => match self.hub.publish(notification.subscription_id, &bundle.to_string()) {
+                0 => Err("No websocket client bound".to_string()),
+                _ => Ok(()),
+            },
+        }
+    }
+
+    /// Match and deliver every `interval`, across tenants
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                // Drain a backlog before delivering, so it goes out in order
+                loop {
+                    match self.dispatch_once().await {
+                        Ok(examined) if examined as i64 == DISPATCH_BATCH => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            tracing::error!("Subscription matching failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                if let Err(e) = self.deliver_once().await {
+                    tracing::error!("Subscription delivery failed: {}", e);
+                }
+            }
+        }))
+    }
+
+    fn row_to_subscription(row: &PgRow) -> Result<Subscription, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let content = PayloadContent::from_string(&row.try_get::<String, _>("content").map_err(read)?);
+        let headers: Value = row.try_get("headers").map_err(read)?;
+
+        Ok(Subscription {
+            resource_type: Subscription::resource_type(),
+            id: Some(row.try_get("id").map_err(read)?),
+            status: SubscriptionStatus::from_string(&row.try_get::<String, _>("status").map_err(read)?),
+            criteria: row.try_get("criteria").map_err(read)?,
+            reason: row.try_get("reason").map_err(read)?,
+            end: row.try_get("end_time").map_err(read)?,
+            error: row.try_get("error").map_err(read)?,
+            channel: SubscriptionChannel {
+                channel_type: ChannelType::from_string(&row.try_get::<String, _>("channel_type").map_err(read)?),
+                endpoint: row.try_get("endpoint").map_err(read)?,
+                payload: (content != PayloadContent::Empty).then(|| "application/fhir+json".to_string()),
+                header: serde_json::from_value(headers).unwrap_or_default(),
+                payload_element: None,
+            }
+            .with_content(content),
+        })
+    }
+
+    fn row_to_notification(row: &PgRow) -> Result<PendingNotification, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let headers: Value = row.try_get("headers").map_err(read)?;
+
+        Ok(PendingNotification {
+            id: row.try_get("id").map_err(read)?,
+            subscription_id: row.try_get("subscription_id").map_err(read)?,
+            event_number: row.try_get("event_number").map_err(read)?,
+            attempts: row.try_get("attempts").map_err(read)?,
+            channel_type: ChannelType::from_string(&row.try_get::<String, _>("channel_type").map_err(read)?),
+            endpoint: row.try_get("endpoint").map_err(read)?,
+            headers: serde_json::from_value(headers).unwrap_or_default(),
+            content: PayloadContent::from_string(&row.try_get::<String, _>("content").map_err(read)?),
+            events_since_start: row.try_get("events_since_start").map_err(read)?,
+            resource_type: row.try_get("resource_type").map_err(read)?,
+            resource_id: row.try_get("resource_id").map_err(read)?,
+            version_id: row.try_get("version_id").map_err(read)?,
+            operation: row.try_get("operation").map_err(read)?,
+            resource: row.try_get("resource").map_err(read)?,
+            changed_at: row.try_get("changed_at").map_err(read)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(payload: Option<&str>) -> SubscriptionChannel {
+        SubscriptionChannel {
+            channel_type: ChannelType::RestHook,
+            endpoint: Some("https://example.org/hook".to_string()),
+            payload: payload.map(str::to_string),
+            header: vec![],
+            payload_element: None,
+        }
+    }
+
+    #[test]
+    fn test_criteria_parsing() {
+        let criteria = SubscriptionCriteria::parse("Appointment?status=booked").unwrap();
+        assert_eq!(criteria.resource_type(), "Appointment");
+        assert!(SubscriptionCriteria::parse("Patient?gender=female&name=ann").is_ok());
+        assert_eq!(
+            SubscriptionCriteria::parse("DocumentReference").unwrap(),
+            SubscriptionCriteria::DocumentReference
+        );
+
+        assert!(SubscriptionCriteria::parse("Patient?_count=10").is_err());
+        assert!(SubscriptionCriteria::parse("Observation?code=1234-5").is_err());
+        assert!(SubscriptionCriteria::parse("Patient?gender=robot").is_err());
+    }
+
+    #[test]
+    fn test_payload_content() {
+        assert_eq!(channel(None).content(), PayloadContent::Empty);
+        assert_eq!(channel(Some("application/fhir+json")).content(), PayloadContent::FullResource);
+        let id_only = channel(Some("application/fhir+json")).with_content(PayloadContent::IdOnly);
+        assert_eq!(id_only.content(), PayloadContent::IdOnly);
+    }
+
+    #[test]
+    fn test_notification_bundle() {
+        let notification = PendingNotification {
+            id: 1,
+            subscription_id: Uuid::new_v4(),
+            event_number: 3,
+            attempts: 0,
+            channel_type: ChannelType::Websocket,
+            endpoint: None,
+            headers: vec![],
+            content: PayloadContent::IdOnly,
+            events_since_start: 3,
+            resource_type: "Appointment".to_string(),
+            resource_id: Uuid::new_v4(),
+            version_id: "2".to_string(),
+            operation: "update".to_string(),
+            resource: json!({ "status": "booked" }),
+            changed_at: Utc::now(),
+        };
+
+        let bundle = notification.bundle();
+        assert_eq!(bundle["type"], "history");
+        // id-only: the status entry names the version but the resource is not sent
+        assert_eq!(bundle["entry"].as_array().unwrap().len(), 1);
+        let event = &bundle["entry"][0]["resource"]["parameter"][4]["part"];
+        assert_eq!(event[0]["valueString"], "3");
+        assert!(event[2]["valueReference"]["reference"].as_str().unwrap().ends_with("/_history/2"));
+
+        let full = PendingNotification { content: PayloadContent::FullResource, ..notification };
+        assert_eq!(full.bundle()["entry"][1]["resource"]["resourceType"], "Appointment");
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(12), Duration::from_secs(3600));
+    }
+}
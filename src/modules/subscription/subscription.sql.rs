@@ -0,0 +1,132 @@
+//! Subscription SQL Queries
+//!
+//! This file contains all SQL queries used by the subscription service
+//! for clean separation of concerns and better maintainability.
+
+/// Insert a new subscription
+pub const INSERT_SUBSCRIPTION: &str = r#"
+    INSERT INTO subscriptions (
+        id, status, criteria, resource_type, reason, channel_type, endpoint, headers, content, end_time, created_by
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+    )
+    RETURNING id, status, criteria, resource_type, reason, channel_type, endpoint, headers, content, end_time,
+        error, events_since_start, created_by, created_at, updated_at
+"#;
+
+/// Get subscription by ID
+pub const GET_SUBSCRIPTION: &str = r#"
+    SELECT id, status, criteria, resource_type, reason, channel_type, endpoint, headers, content, end_time,
+        error, events_since_start, created_by, created_at, updated_at
+    FROM subscriptions
+    WHERE id = $1
+"#;
+
+/// List subscriptions, newest first
+pub const LIST_SUBSCRIPTIONS: &str = r#"
+    SELECT id, status, criteria, resource_type, reason, channel_type, endpoint, headers, content, end_time,
+        error, events_since_start, created_by, created_at, updated_at
+    FROM subscriptions
+    ORDER BY created_at DESC
+"#;
+
+/// Replace a subscription's settings. Re-activating clears the last error.
+pub const UPDATE_SUBSCRIPTION: &str = r#"
+    UPDATE subscriptions
+    SET status = $2, criteria = $3, resource_type = $4, reason = $5, channel_type = $6, endpoint = $7,
+        headers = $8, content = $9, end_time = $10,
+        error = CASE WHEN $2 = 'active' THEN NULL ELSE error END
+    WHERE id = $1
+    RETURNING id, status, criteria, resource_type, reason, channel_type, endpoint, headers, content, end_time,
+        error, events_since_start, created_by, created_at, updated_at
+"#;
+
+/// Delete a subscription and its queued notifications
+pub const DELETE_SUBSCRIPTION: &str = r#"
+    DELETE FROM subscriptions WHERE id = $1
+"#;
+
+/// Lock the dispatcher's position, so only one server matches at a time
+pub const LOCK_DISPATCH_STATE: &str = r#"
+    SELECT last_history_id FROM subscription_dispatch_state FOR UPDATE
+"#;
+
+pub const SAVE_DISPATCH_STATE: &str = r#"
+    UPDATE subscription_dispatch_state SET last_history_id = $1
+"#;
+
+/// Created and updated resource versions written since the last pass.
+/// Versions from the last few seconds wait for the next pass, so that a
+/// transaction which took an earlier id but commits later is not skipped.
+pub const GET_NEW_VERSIONS: &str = r#"
+    SELECT id, tenant_id, resource_type, resource_id
+    FROM resource_history
+    WHERE id > $1 AND changed_at < NOW() - INTERVAL '5 seconds' AND operation IN ('create', 'update')
+    ORDER BY id
+    LIMIT $2
+"#;
+
+/// Subscriptions that can receive notifications now
+pub const GET_ACTIVE_SUBSCRIPTIONS: &str = r#"
+    SELECT id, tenant_id, criteria
+    FROM subscriptions
+    WHERE status = 'active' AND (end_time IS NULL OR end_time > NOW())
+"#;
+
+/// Subscriptions whose end time has passed are switched off
+pub const EXPIRE_SUBSCRIPTIONS: &str = r#"
+    UPDATE subscriptions SET status = 'off'
+    WHERE status IN ('requested', 'active') AND end_time <= NOW()
+"#;
+
+/// Queue a notification under the subscription's next event number
+pub const INSERT_NOTIFICATION: &str = r#"
+    WITH event AS (
+        UPDATE subscriptions SET events_since_start = events_since_start + 1
+        WHERE id = $1
+        RETURNING tenant_id, events_since_start
+    )
+    INSERT INTO subscription_notifications (tenant_id, subscription_id, history_id, event_number)
+    SELECT tenant_id, $1, $2, events_since_start FROM event
+    ON CONFLICT (subscription_id, history_id) DO NOTHING
+"#;
+
+/// Notifications due for delivery, with what is needed to send them.
+/// Rows are locked so concurrent servers deliver different ones.
+pub const GET_DUE_NOTIFICATIONS: &str = r#"
+    SELECT n.id, n.subscription_id, n.event_number, n.attempts,
+        s.channel_type, s.endpoint, s.headers, s.content, s.events_since_start,
+        h.resource_type, h.resource_id, h.version_id, h.operation, h.resource, h.changed_at
+    FROM subscription_notifications n
+    JOIN subscriptions s ON s.id = n.subscription_id
+    JOIN resource_history h ON h.id = n.history_id
+    WHERE n.status = 'pending' AND n.next_attempt_at <= NOW() AND s.status = 'active'
+    ORDER BY n.id
+    LIMIT $1
+    FOR UPDATE OF n SKIP LOCKED
+"#;
+
+pub const MARK_NOTIFICATION_DELIVERED: &str = r#"
+    UPDATE subscription_notifications
+    SET status = 'delivered', attempts = attempts + 1, delivered_at = NOW(), last_error = NULL
+    WHERE id = $1
+"#;
+
+/// Record a failed attempt and schedule the next one
+pub const RETRY_NOTIFICATION: &str = r#"
+    UPDATE subscription_notifications
+    SET attempts = attempts + 1, last_error = $2, next_attempt_at = NOW() + make_interval(secs => $3)
+    WHERE id = $1
+"#;
+
+/// Give up on a notification after its last attempt
+pub const FAIL_NOTIFICATION: &str = r#"
+    UPDATE subscription_notifications
+    SET status = 'failed', attempts = attempts + 1, last_error = $2
+    WHERE id = $1
+"#;
+
+/// A subscription whose endpoint keeps failing stops being sent to
+pub const SET_SUBSCRIPTION_ERROR: &str = r#"
+    UPDATE subscriptions SET status = 'error', error = $2 WHERE id = $1
+"#;
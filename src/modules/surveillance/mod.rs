@@ -0,0 +1,59 @@
+//! Surveillance Module
+//!
+//! This module provides notifiable disease reporting to public health:
+//! - Configurable notifiable disease code lists in ICD-10 and SNOMED CT
+//! - Detection of cases as diagnoses on the lists are recorded, and
+//!   cancellation when they are refuted or removed
+//! - Case reports as FHIR Bundles or India's IDSP case records
+//! - Submission status and reporting deadlines, with overdue reports listed
+//!   soonest due first
+
+#[path = "surveillance.controller.rs"]
+pub mod surveillance_controller;
+#[path = "surveillance.service.rs"]
+pub mod surveillance_service;
+#[path = "surveillance.sql.rs"]
+pub mod surveillance_sql;
+#[path = "surveillance.format.rs"]
+pub mod surveillance_format;
+
+pub use surveillance_controller::SurveillanceController;
+pub use surveillance_service::{CaseReport, NotifiableDisease, SurveillanceConfig, SurveillanceService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::condition::ConditionService;
+use crate::modules::patient::PatientService;
+use crate::utils::api_router::ApiRouter;
+
+/// Surveillance Module Configuration
+pub struct SurveillanceModule {
+    pub service: Arc<SurveillanceService>,
+    pub controller: Arc<SurveillanceController>,
+}
+
+impl SurveillanceModule {
+    /// Create a new Surveillance Module with dependency injection
+    pub fn new(
+        db_pool: PgPool,
+        conditions: Arc<ConditionService>,
+        patients: Arc<PatientService>,
+        config: SurveillanceConfig,
+    ) -> Self {
+        let service = Arc::new(SurveillanceService::new(db_pool, conditions, patients, config));
+        let controller = Arc::new(SurveillanceController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<SurveillanceService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,230 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::surveillance::surveillance_service::{
+    CaseReport, CaseReportQuery, NotifiableDisease, NotifiableDiseaseRequest, ReportFormat,
+};
+use crate::modules::surveillance::SurveillanceService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PayloadQuery {
+    /// Render in another format than the disease's own
+    pub format: Option<ReportFormat>,
+}
+
+/// A report as submitted to the authority
+#[derive(Debug, Default, Deserialize)]
+pub struct SubmitRequest {
+    /// The authority's reference for the submission
+    pub reference: Option<String>,
+}
+
+/// The authority's answer to a submission
+#[derive(Debug, Deserialize)]
+pub struct ResponseRequest {
+    pub accepted: bool,
+    /// The acknowledgement, or why the report was rejected
+    pub response: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Surveillance controller for notifiable diseases and their case reports
+pub struct SurveillanceController {
+    surveillance_service: Arc<SurveillanceService>,
+}
+
+impl SurveillanceController {
+    /// Create new controller with injected service
+    pub fn new(surveillance_service: Arc<SurveillanceService>) -> Self {
+        Self { surveillance_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/diseases", Self::create_disease, "Add a notifiable disease")
+            .get("/diseases", Self::list_diseases, "List notifiable diseases")
+            .get("/diseases/:id", Self::get_disease, "Get notifiable disease by ID")
+            .put("/diseases/:id", Self::update_disease, "Update a notifiable disease")
+            .get("/case-reports", Self::list_reports, "List case reports, soonest due first")
+            .get("/case-reports/:id", Self::get_report, "Get case report by ID")
+            .get("/case-reports/:id/payload", Self::report_payload, "Render a case report for submission")
+            .post("/case-reports/:id/submit", Self::submit_report, "Record a case report as submitted")
+            .post("/case-reports/:id/response", Self::record_response, "Record the authority's answer to a case report")
+            .post("/case-reports/:id/cancel", Self::cancel_report, "Cancel a case report")
+            .with_state(self.surveillance_service.clone())
+    }
+
+    pub async fn create_disease(
+        State(service): State<Arc<SurveillanceService>>,
+        Json(request): Json<NotifiableDiseaseRequest>,
+    ) -> Result<(StatusCode, Json<NotifiableDisease>), ErrorReply> {
+        tracing::info!("Adding notifiable disease {}", request.code);
+
+        match service.create_disease(request).await {
+            Ok(disease) => Ok((StatusCode::CREATED, Json(disease))),
+            Err(e) => Err(Self::error_response("Failed to add notifiable disease", e)),
+        }
+    }
+
+    pub async fn list_diseases(
+        State(service): State<Arc<SurveillanceService>>,
+    ) -> Result<Json<Vec<NotifiableDisease>>, ErrorReply> {
+        match service.list_diseases().await {
+            Ok(diseases) => Ok(Json(diseases)),
+            Err(e) => Err(Self::error_response("Failed to list notifiable diseases", e)),
+        }
+    }
+
+    pub async fn get_disease(
+        State(service): State<Arc<SurveillanceService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<NotifiableDisease>, ErrorReply> {
+        match service.get_disease(id).await {
+            Ok(Some(disease)) => Ok(Json(disease)),
+            Ok(None) => Err(Self::not_found("Notifiable disease", id)),
+            Err(e) => Err(Self::error_response("Failed to get notifiable disease", e)),
+        }
+    }
+
+    pub async fn update_disease(
+        State(service): State<Arc<SurveillanceService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<NotifiableDiseaseRequest>,
+    ) -> Result<Json<NotifiableDisease>, ErrorReply> {
+        tracing::info!("Updating notifiable disease {}", id);
+
+        match service.update_disease(id, request).await {
+            Ok(Some(disease)) => Ok(Json(disease)),
+            Ok(None) => Err(Self::not_found("Notifiable disease", id)),
+            Err(e) => Err(Self::error_response("Failed to update notifiable disease", e)),
+        }
+    }
+
+    pub async fn list_reports(
+        State(service): State<Arc<SurveillanceService>>,
+        Query(query): Query<CaseReportQuery>,
+    ) -> Result<Json<Vec<CaseReport>>, ErrorReply> {
+        match service.list_reports(&query).await {
+            Ok(reports) => Ok(Json(reports)),
+            Err(e) => Err(Self::error_response("Failed to list case reports", e)),
+        }
+    }
+
+    pub async fn get_report(
+        State(service): State<Arc<SurveillanceService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<CaseReport>, ErrorReply> {
+        match service.get_report(id).await {
+            Ok(Some(report)) => Ok(Json(report)),
+            Ok(None) => Err(Self::not_found("Case report", id)),
+            Err(e) => Err(Self::error_response("Failed to get case report", e)),
+        }
+    }
+
+    /// The report as it would be submitted now, in the disease's format
+    /// unless another is asked for
+    pub async fn report_payload(
+        State(service): State<Arc<SurveillanceService>>,
+        Path(id): Path<Uuid>,
+        Query(query): Query<PayloadQuery>,
+    ) -> Result<Json<serde_json::Value>, ErrorReply> {
+        match service.report_payload(id, query.format).await {
+            Ok(Some(payload)) => Ok(Json(payload)),
+            Ok(None) => Err(Self::not_found("Case report", id)),
+            Err(e) => Err(Self::error_response("Failed to render case report", e)),
+        }
+    }
+
+    pub async fn submit_report(
+        State(service): State<Arc<SurveillanceService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<SubmitRequest>,
+    ) -> Result<Json<CaseReport>, ErrorReply> {
+        tracing::info!("Submitting case report {}", id);
+
+        match service.submit_report(id, request.reference, extract_user_from_headers(&headers).ok()).await {
+            Ok(Some(report)) => Ok(Json(report)),
+            Ok(None) => Err(Self::not_found("Case report", id)),
+            Err(e) => Err(Self::error_response("Failed to submit case report", e)),
+        }
+    }
+
+    pub async fn record_response(
+        State(service): State<Arc<SurveillanceService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<ResponseRequest>,
+    ) -> Result<Json<CaseReport>, ErrorReply> {
+        tracing::info!("Recording response to case report {}", id);
+
+        match service.record_response(id, request.accepted, request.response).await {
+            Ok(Some(report)) => Ok(Json(report)),
+            Ok(None) => Err(Self::not_found("Case report", id)),
+            Err(e) => Err(Self::error_response("Failed to record case report response", e)),
+        }
+    }
+
+    pub async fn cancel_report(
+        State(service): State<Arc<SurveillanceService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<CancelRequest>,
+    ) -> Result<Json<CaseReport>, ErrorReply> {
+        tracing::info!("Cancelling case report {}", id);
+
+        match service.cancel_report(id, request.reason).await {
+            Ok(Some(report)) => Ok(Json(report)),
+            Ok(None) => Err(Self::not_found("Case report", id)),
+            Err(e) => Err(Self::error_response("Failed to cancel case report", e)),
+        }
+    }
+
+    fn not_found(what: &str, id: Uuid) -> ErrorReply {
+        tracing::warn!("{} not found: {}", what, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", what),
+                message: format!("{} with id {} not found", what, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{Datelike, FixedOffset, NaiveDate};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::models::constants::{BUNDLE_RESOURCE_TYPE, ICD_10_CM_SYSTEM, ICD_10_SYSTEM, NOTIFIABLE_DISEASE_SYSTEM};
+use crate::models::{CodeableConcept, Condition, ConditionVerificationStatus, Patient};
+use crate::modules::condition::ConditionController;
+use crate::modules::patient::PatientController;
+use crate::modules::surveillance::surveillance_service::{CaseReport, NotifiableDisease};
+use crate::modules::vital_record::vital_record_export::{address, age, display_name, sex};
+
+/// The facility as it is known to the surveillance programme it reports to
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportingUnit {
+    pub name: Option<String>,
+    pub district: Option<String>,
+    pub state: Option<String>,
+}
+
+/// A case report as a FHIR collection Bundle of the patient and the
+/// diagnosis, tagged with the notifiable disease
+pub fn fhir_case_report(
+    report: &CaseReport,
+    disease: &NotifiableDisease,
+    condition: Condition,
+    patient: Patient,
+) -> Value {
+    let patient_url = format!("Patient/{}", patient.id);
+    let condition_url = format!("Condition/{}", condition.id);
+    json!({
+        "resourceType": BUNDLE_RESOURCE_TYPE,
+        "id": report.id,
+        "meta": {
+            "tag": [{
+                "system": NOTIFIABLE_DISEASE_SYSTEM,
+                "code": disease.code,
+                "display": disease.name,
+            }],
+        },
+        "type": "collection",
+        "timestamp": report.detected_at,
+        "entry": [
+            { "fullUrl": patient_url, "resource": PatientController::patient_to_response(patient) },
+            { "fullUrl": condition_url, "resource": ConditionController::condition_to_response(condition) },
+        ],
+    })
+}
+
+/// The diagnosis's ICD-10 code, if it was coded in ICD-10
+fn icd10_code(code: &CodeableConcept) -> Option<String> {
+    code.coding
+        .iter()
+        .find(|coding| matches!(coding.system.as_deref(), Some(ICD_10_SYSTEM | ICD_10_CM_SYSTEM)))
+        .and_then(|coding| coding.code.clone())
+}
+
+/// The IDSP reporting form a diagnosis goes on: L for laboratory confirmed
+/// cases, P for presumptive ones a clinician diagnosed
+fn idsp_form(verification_status: &ConditionVerificationStatus) -> &'static str {
+    match verification_status {
+        ConditionVerificationStatus::Confirmed => "L",
+        _ => "P",
+    }
+}
+
+/// A case report as an IDSP case record: the reporting unit, the
+/// epidemiological (ISO) week and the case, with dates in local time
+pub fn idsp_case_report(
+    report: &CaseReport,
+    disease: &NotifiableDisease,
+    condition: &Condition,
+    patient: &Patient,
+    unit: &ReportingUnit,
+    offset: FixedOffset,
+) -> Value {
+    let local_date = |at: chrono::DateTime<chrono::Utc>| -> NaiveDate { at.with_timezone(&offset).date_naive() };
+    let diagnosed = local_date(condition.recorded_date);
+    let week = diagnosed.iso_week();
+
+    json!({
+        "form": idsp_form(&condition.verification_status),
+        "reporting_unit": unit,
+        "epi_week": { "year": week.year(), "week": week.week() },
+        "disease": {
+            "code": disease.code,
+            "name": disease.name,
+            "icd10": icd10_code(&condition.code),
+        },
+        "case": {
+            "id": report.id,
+            "patient_id": patient.id,
+            "name": display_name(patient),
+            "age": patient.birth_date.and_then(|born| age(born, diagnosed)),
+            "sex": sex(&patient.gender),
+            "address": address(patient),
+        },
+        "date_of_onset": condition.onset.map(local_date),
+        "date_of_diagnosis": diagnosed,
+        "date_of_report": local_date(report.detected_at),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Coding, ConditionCategory, Gender, HumanName};
+    use crate::modules::surveillance::surveillance_service::{CaseReportStatus, ReportFormat};
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn case() -> (CaseReport, NotifiableDisease, Condition, Patient) {
+        let patient = Patient::new(
+            vec![HumanName {
+                use_type: None,
+                text: None,
+                family: Some("Kumar".to_string()),
+                given: vec!["Arjun".to_string()],
+                prefix: Vec::new(),
+                suffix: Vec::new(),
+            }],
+            Vec::new(),
+            Gender::Male,
+            NaiveDate::from_ymd_opt(1990, 6, 15),
+        );
+        let code = CodeableConcept {
+            coding: vec![Coding {
+                system: Some(ICD_10_SYSTEM.to_string()),
+                version: None,
+                code: Some("A00.9".to_string()),
+                display: Some("Cholera, unspecified".to_string()),
+            }],
+            text: None,
+        };
+        let mut condition = Condition::new(patient.id, code, ConditionCategory::EncounterDiagnosis);
+        // Late on Sunday 7 January in UTC is Monday 8 January in India
+        condition.recorded_date = Utc.with_ymd_and_hms(2024, 1, 7, 20, 0, 0).unwrap();
+        condition.verification_status = ConditionVerificationStatus::Provisional;
+
+        let now = Utc::now();
+        let disease = NotifiableDisease {
+            id: Uuid::new_v4(),
+            code: "cholera".to_string(),
+            name: "Cholera".to_string(),
+            icd10_codes: vec!["A00".to_string()],
+            snomed_codes: Vec::new(),
+            report_format: ReportFormat::Idsp,
+            deadline_hours: 24,
+            report_suspected: true,
+            active: true,
+            created_at: now,
+            updated_at: now,
+        };
+        let report = CaseReport {
+            id: Uuid::new_v4(),
+            disease_id: disease.id,
+            patient_id: patient.id,
+            condition_id: condition.id,
+            encounter_id: None,
+            status: CaseReportStatus::Pending,
+            report_format: ReportFormat::Idsp,
+            detected_at: condition.recorded_date,
+            due_at: condition.recorded_date + chrono::Duration::hours(24),
+            overdue: false,
+            submitted_at: None,
+            submitted_by: None,
+            submission_reference: None,
+            payload: None,
+            response: None,
+            responded_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+        (report, disease, condition, patient)
+    }
+
+    #[test]
+    fn test_fhir_case_report() {
+        let (report, disease, condition, patient) = case();
+        let bundle = fhir_case_report(&report, &disease, condition, patient);
+
+        assert_eq!(bundle["resourceType"], "Bundle");
+        assert_eq!(bundle["type"], "collection");
+        assert_eq!(bundle["meta"]["tag"][0]["code"], "cholera");
+        assert_eq!(bundle["entry"][0]["resource"]["resourceType"], "Patient");
+        assert_eq!(bundle["entry"][1]["resource"]["resourceType"], "Condition");
+        assert_eq!(bundle["entry"][1]["resource"]["code"]["coding"][0]["code"], "A00.9");
+    }
+
+    #[test]
+    fn test_idsp_case_report() {
+        let (report, disease, mut condition, patient) = case();
+        let unit = ReportingUnit {
+            name: Some("District Hospital".to_string()),
+            district: Some("Pune".to_string()),
+            state: Some("Maharashtra".to_string()),
+        };
+        let ist = FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+
+        let record = idsp_case_report(&report, &disease, &condition, &patient, &unit, ist);
+        assert_eq!(record["form"], "P");
+        assert_eq!(record["reporting_unit"]["district"], "Pune");
+        assert_eq!(record["epi_week"], json!({ "year": 2024, "week": 2 }));
+        assert_eq!(record["disease"]["icd10"], "A00.9");
+        assert_eq!(record["case"]["name"], "Arjun Kumar");
+        assert_eq!(record["case"]["age"], "33 years");
+        assert_eq!(record["case"]["sex"], "Male");
+        assert_eq!(record["date_of_diagnosis"], "2024-01-08");
+        assert_eq!(record["date_of_onset"], Value::Null);
+
+        condition.verification_status = ConditionVerificationStatus::Confirmed;
+        let record = idsp_case_report(&report, &disease, &condition, &patient, &unit, ist);
+        assert_eq!(record["form"], "L");
+    }
+}
@@ -0,0 +1,861 @@
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::models::constants::{ICD_10_CM_SYSTEM, ICD_10_SYSTEM, SNOMED_CT_SYSTEM};
+use crate::models::{CodeableConcept, Condition, ConditionVerificationStatus, Patient};
+use crate::modules::condition::ConditionService;
+use crate::modules::events::{DomainEvent, EventBus, EventEnvelope};
+use crate::modules::patient::PatientService;
+use crate::modules::surveillance::surveillance_format::{fhir_case_report, idsp_case_report, ReportingUnit};
+
+// Import SQL queries
+use crate::modules::surveillance::surveillance_sql::*;
+
+/// Surveillance settings
+#[derive(Debug, Clone)]
+pub struct SurveillanceConfig {
+    /// The facility as it reports to public health
+    pub reporting_unit: ReportingUnit,
+    /// Offset of the local time reports give dates in
+    pub utc_offset: FixedOffset,
+}
+
+impl Default for SurveillanceConfig {
+    fn default() -> Self {
+        Self {
+            reporting_unit: ReportingUnit::default(),
+            utc_offset: FixedOffset::east_opt(0).expect("UTC is a valid offset"),
+        }
+    }
+}
+
+impl SurveillanceConfig {
+    /// Settings from `SURVEILLANCE_REPORTING_UNIT`, `SURVEILLANCE_DISTRICT`,
+    /// `SURVEILLANCE_STATE` and `SURVEILLANCE_UTC_OFFSET`, e.g. `+05:30`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        Self {
+            reporting_unit: ReportingUnit {
+                name: var("SURVEILLANCE_REPORTING_UNIT"),
+                district: var("SURVEILLANCE_DISTRICT"),
+                state: var("SURVEILLANCE_STATE"),
+            },
+            utc_offset: var("SURVEILLANCE_UTC_OFFSET")
+                .and_then(|offset| offset.trim().parse().ok())
+                .unwrap_or(defaults.utc_offset),
+        }
+    }
+}
+
+/// Format a case report is sent to the authority in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    /// A FHIR Bundle of the patient and the diagnosis
+    Fhir,
+    /// India's Integrated Disease Surveillance Programme case record
+    Idsp,
+}
+
+impl ReportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportFormat::Fhir => "fhir",
+            ReportFormat::Idsp => "idsp",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "fhir" => Some(ReportFormat::Fhir),
+            "idsp" => Some(ReportFormat::Idsp),
+            _ => None,
+        }
+    }
+}
+
+/// A disease to be notified to public health
+#[derive(Debug, Clone, Serialize)]
+pub struct NotifiableDisease {
+    pub id: Uuid,
+    /// Short code, e.g. `cholera`
+    pub code: String,
+    pub name: String,
+    /// ICD-10 codes, each matching itself and the codes below it
+    pub icd10_codes: Vec<String>,
+    /// SNOMED CT concept IDs, matched exactly
+    pub snomed_codes: Vec<String>,
+    pub report_format: ReportFormat,
+    /// Hours from diagnosis within which a case is to be reported
+    pub deadline_hours: i32,
+    /// Whether suspected diagnoses are reported, or only confirmed ones
+    pub report_suspected: bool,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An ICD-10 code without its dot, upper-case, e.g. `A009`
+fn compact_icd10(code: &str) -> String {
+    code.trim().to_ascii_uppercase().chars().filter(|c| *c != '.').collect()
+}
+
+impl NotifiableDisease {
+    /// Whether a diagnosis is coded as this disease
+    pub fn matches(&self, code: &CodeableConcept) -> bool {
+        code.coding.iter().any(|coding| {
+            let Some(value) = coding.code.as_deref() else {
+                return false;
+            };
+            match coding.system.as_deref() {
+                Some(ICD_10_SYSTEM | ICD_10_CM_SYSTEM) => {
+                    let value = compact_icd10(value);
+                    self.icd10_codes.iter().any(|listed| value.starts_with(&compact_icd10(listed)))
+                }
+                Some(SNOMED_CT_SYSTEM) => self.snomed_codes.iter().any(|listed| listed == value),
+                _ => false,
+            }
+        })
+    }
+
+    /// Whether a diagnosis of this disease with a verification status is to
+    /// be reported
+    pub fn reports(&self, verification_status: &ConditionVerificationStatus) -> bool {
+        match verification_status {
+            ConditionVerificationStatus::Confirmed => true,
+            ConditionVerificationStatus::Unconfirmed
+            | ConditionVerificationStatus::Provisional
+            | ConditionVerificationStatus::Differential => self.report_suspected,
+            ConditionVerificationStatus::Refuted | ConditionVerificationStatus::EnteredInError => false,
+        }
+    }
+}
+
+fn disease_from_row(row: &PgRow) -> NotifiableDisease {
+    NotifiableDisease {
+        id: row.get("id"),
+        code: row.get("code"),
+        name: row.get("name"),
+        icd10_codes: row.get("icd10_codes"),
+        snomed_codes: row.get("snomed_codes"),
+        report_format: ReportFormat::from_string(row.get("report_format")).unwrap_or(ReportFormat::Fhir),
+        deadline_hours: row.get("deadline_hours"),
+        report_suspected: row.get("report_suspected"),
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// A notifiable disease as admins configure it
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotifiableDiseaseRequest {
+    pub code: String,
+    pub name: String,
+    #[serde(default)]
+    pub icd10_codes: Vec<String>,
+    #[serde(default)]
+    pub snomed_codes: Vec<String>,
+    pub report_format: ReportFormat,
+    pub deadline_hours: i32,
+    #[serde(default = "default_true")]
+    pub report_suspected: bool,
+    #[serde(default = "default_true")]
+    pub active: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl NotifiableDiseaseRequest {
+    /// Check the request and tidy its code lists
+    fn check(&mut self) -> Result<(), String> {
+        self.code = self.code.trim().to_string();
+        if self.code.is_empty() || self.name.trim().is_empty() {
+            return Err("A notifiable disease needs a code and a name".to_string());
+        }
+        if self.deadline_hours <= 0 {
+            return Err("A reporting deadline is a positive number of hours".to_string());
+        }
+        self.icd10_codes = self.icd10_codes.iter().map(|code| compact_icd10(code)).collect();
+        self.snomed_codes = self.snomed_codes.iter().map(|code| code.trim().to_string()).collect();
+        if let Some(code) = self.icd10_codes.iter().find(|code| {
+            let chars: Vec<char> = code.chars().collect();
+            chars.len() < 3
+                || !chars[0].is_ascii_uppercase()
+                || !chars[1].is_ascii_digit()
+                || !chars[2].is_ascii_digit()
+        }) {
+            return Err(format!("{:?} is not an ICD-10 code or category", code));
+        }
+        if let Some(code) = self
+            .snomed_codes
+            .iter()
+            .find(|code| code.is_empty() || !code.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(format!("{:?} is not a SNOMED CT concept ID", code));
+        }
+        if self.icd10_codes.is_empty() && self.snomed_codes.is_empty() {
+            return Err("A notifiable disease needs at least one ICD-10 or SNOMED CT code".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Where a case report is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseReportStatus {
+    /// Detected, to be submitted by its due time
+    Pending,
+    Submitted,
+    Acknowledged,
+    /// Rejected by the authority, to be corrected and submitted again
+    Rejected,
+    /// Not to be reported, e.g. the diagnosis was refuted
+    Cancelled,
+}
+
+impl CaseReportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaseReportStatus::Pending => "pending",
+            CaseReportStatus::Submitted => "submitted",
+            CaseReportStatus::Acknowledged => "acknowledged",
+            CaseReportStatus::Rejected => "rejected",
+            CaseReportStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(CaseReportStatus::Pending),
+            "submitted" => Some(CaseReportStatus::Submitted),
+            "acknowledged" => Some(CaseReportStatus::Acknowledged),
+            "rejected" => Some(CaseReportStatus::Rejected),
+            "cancelled" => Some(CaseReportStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether the report is still to be submitted
+    pub fn is_outstanding(&self) -> bool {
+        matches!(self, CaseReportStatus::Pending | CaseReportStatus::Rejected)
+    }
+}
+
+/// A case of a notifiable disease to report
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseReport {
+    pub id: Uuid,
+    pub disease_id: Uuid,
+    pub patient_id: Uuid,
+    /// The diagnosis the case was detected in
+    pub condition_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub status: CaseReportStatus,
+    pub report_format: ReportFormat,
+    pub detected_at: DateTime<Utc>,
+    pub due_at: DateTime<Utc>,
+    /// Still to be submitted and past its due time
+    pub overdue: bool,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub submitted_by: Option<Uuid>,
+    pub submission_reference: Option<String>,
+    /// The report as last submitted
+    pub payload: Option<serde_json::Value>,
+    /// The authority's answer, or why the report was cancelled
+    pub response: Option<String>,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn case_report_from_row(row: &PgRow) -> CaseReport {
+    let status = CaseReportStatus::from_string(row.get("status")).unwrap_or(CaseReportStatus::Pending);
+    let due_at: DateTime<Utc> = row.get("due_at");
+    CaseReport {
+        id: row.get("id"),
+        disease_id: row.get("disease_id"),
+        patient_id: row.get("patient_id"),
+        condition_id: row.get("condition_id"),
+        encounter_id: row.get("encounter_id"),
+        status,
+        report_format: ReportFormat::from_string(row.get("report_format")).unwrap_or(ReportFormat::Fhir),
+        detected_at: row.get("detected_at"),
+        due_at,
+        overdue: status.is_outstanding() && due_at < Utc::now(),
+        submitted_at: row.get("submitted_at"),
+        submitted_by: row.get("submitted_by"),
+        submission_reference: row.get("submission_reference"),
+        payload: row.get("payload"),
+        response: row.get("response"),
+        responded_at: row.get("responded_at"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+impl CaseReport {
+    /// Record the report as submitted with its payload, the first time or
+    /// again after a rejection
+    pub fn submit(
+        &mut self,
+        payload: serde_json::Value,
+        reference: Option<String>,
+        now: DateTime<Utc>,
+        submitted_by: Option<Uuid>,
+    ) -> Result<(), String> {
+        if !self.status.is_outstanding() {
+            return Err(format!("Case report {} is {}", self.id, self.status.as_str()));
+        }
+        self.status = CaseReportStatus::Submitted;
+        self.payload = Some(payload);
+        self.submission_reference = reference;
+        self.submitted_at = Some(now);
+        self.submitted_by = submitted_by;
+        self.response = None;
+        self.responded_at = None;
+        self.overdue = false;
+        Ok(())
+    }
+
+    /// Record the authority's answer to a submission
+    pub fn respond(&mut self, accepted: bool, response: Option<String>, now: DateTime<Utc>) -> Result<(), String> {
+        if self.status != CaseReportStatus::Submitted {
+            return Err(format!("Case report {} is {}, not submitted", self.id, self.status.as_str()));
+        }
+        self.status = match accepted {
+            true => CaseReportStatus::Acknowledged,
+            false => CaseReportStatus::Rejected,
+        };
+        self.response = response;
+        self.responded_at = Some(now);
+        self.overdue = !accepted && self.due_at < now;
+        Ok(())
+    }
+
+    /// Withdraw a report that is not to be submitted
+    pub fn cancel(&mut self, reason: String, now: DateTime<Utc>) -> Result<(), String> {
+        if !self.status.is_outstanding() {
+            return Err(format!("Case report {} is {}", self.id, self.status.as_str()));
+        }
+        self.status = CaseReportStatus::Cancelled;
+        self.response = Some(reason);
+        self.responded_at = Some(now);
+        self.overdue = false;
+        Ok(())
+    }
+}
+
+/// Which case reports to list
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CaseReportQuery {
+    pub status: Option<CaseReportStatus>,
+    pub disease_id: Option<Uuid>,
+    pub patient_id: Option<Uuid>,
+    /// Only those still to be submitted and past their due time
+    #[serde(default)]
+    pub overdue: bool,
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Database error, or a conflict when a unique constraint was violated
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+/// Surveillance service for notifiable disease code lists, the case
+/// reports detected from diagnoses on them and their submission
+pub struct SurveillanceService {
+    pool: PgPool,
+    conditions: Arc<ConditionService>,
+    patients: Arc<PatientService>,
+    config: SurveillanceConfig,
+}
+
+impl SurveillanceService {
+    /// Create new surveillance service
+    pub fn new(
+        pool: PgPool,
+        conditions: Arc<ConditionService>,
+        patients: Arc<PatientService>,
+        config: SurveillanceConfig,
+    ) -> Self {
+        Self {
+            pool,
+            conditions,
+            patients,
+            config,
+        }
+    }
+
+    /// Add a notifiable disease
+    pub async fn create_disease(&self, mut request: NotifiableDiseaseRequest) -> Result<NotifiableDisease, HimsError> {
+        request.check().map_err(|message| HimsError::ValidationError { message })?;
+
+        let row = sqlx::query(INSERT_DISEASE)
+            .bind(Uuid::new_v4())
+            .bind(&request.code)
+            .bind(request.name.trim())
+            .bind(&request.icd10_codes)
+            .bind(&request.snomed_codes)
+            .bind(request.report_format.as_str())
+            .bind(request.deadline_hours)
+            .bind(request.report_suspected)
+            .bind(request.active)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("Notifiable disease {} already exists", request.code)))?;
+        Ok(disease_from_row(&row))
+    }
+
+    /// Get notifiable disease by ID
+    pub async fn get_disease(&self, id: Uuid) -> Result<Option<NotifiableDisease>, HimsError> {
+        let row = sqlx::query(GET_DISEASE_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(disease_from_row))
+    }
+
+    /// All notifiable diseases, by name
+    pub async fn list_diseases(&self) -> Result<Vec<NotifiableDisease>, HimsError> {
+        let rows = sqlx::query(LIST_DISEASES)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(disease_from_row).collect())
+    }
+
+    /// Replace a notifiable disease; reports already detected keep their
+    /// format and due time. `None` if there is no such disease.
+    pub async fn update_disease(
+        &self,
+        id: Uuid,
+        mut request: NotifiableDiseaseRequest,
+    ) -> Result<Option<NotifiableDisease>, HimsError> {
+        request.check().map_err(|message| HimsError::ValidationError { message })?;
+
+        let row = sqlx::query(UPDATE_DISEASE)
+            .bind(id)
+            .bind(&request.code)
+            .bind(request.name.trim())
+            .bind(&request.icd10_codes)
+            .bind(&request.snomed_codes)
+            .bind(request.report_format.as_str())
+            .bind(request.deadline_hours)
+            .bind(request.report_suspected)
+            .bind(request.active)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("Notifiable disease {} already exists", request.code)))?;
+        Ok(row.as_ref().map(disease_from_row))
+    }
+
+    /// Open case reports for a diagnosis on the tenant's active disease
+    /// lists, and cancel those still unsubmitted once it is refuted.
+    /// Returns how many reports were opened.
+    pub async fn detect(&self, tenant_id: Uuid, condition: &Condition) -> Result<usize, HimsError> {
+        if matches!(
+            condition.verification_status,
+            ConditionVerificationStatus::Refuted | ConditionVerificationStatus::EnteredInError
+        ) {
+            self.cancel_for_condition(condition.id, "The diagnosis was refuted").await?;
+            return Ok(0);
+        }
+
+        let diseases: Vec<NotifiableDisease> = sqlx::query(GET_TENANT_ACTIVE_DISEASES)
+            .bind(tenant_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(disease_from_row)
+            .collect();
+
+        let mut opened = 0;
+        for disease in diseases
+            .iter()
+            .filter(|disease| disease.matches(&condition.code) && disease.reports(&condition.verification_status))
+        {
+            let detected_at = Utc::now();
+            let inserted = sqlx::query(INSERT_CASE_REPORT)
+                .bind(Uuid::new_v4())
+                .bind(tenant_id)
+                .bind(disease.id)
+                .bind(condition.patient_id)
+                .bind(condition.id)
+                .bind(condition.encounter_id)
+                .bind(disease.report_format.as_str())
+                .bind(detected_at)
+                .bind(detected_at + Duration::hours(disease.deadline_hours.into()))
+                .execute(&self.pool)
+                .await
+                .map_err(database_error)?
+                .rows_affected();
+            if inserted > 0 {
+                tracing::info!("Detected case of {} in condition {}", disease.code, condition.id);
+                opened += 1;
+            }
+        }
+        Ok(opened)
+    }
+
+    async fn cancel_for_condition(&self, condition_id: Uuid, reason: &str) -> Result<u64, HimsError> {
+        let cancelled = sqlx::query(CANCEL_CONDITION_REPORTS)
+            .bind(condition_id)
+            .bind(reason)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?
+            .rows_affected();
+        Ok(cancelled)
+    }
+
+    /// Detect cases in the diagnosis a condition event is about
+    pub async fn handle_event(&self, envelope: &EventEnvelope) -> Result<(), HimsError> {
+        let condition_id = match &envelope.event {
+            DomainEvent::ConditionRecorded { condition_id, .. } | DomainEvent::ConditionUpdated { condition_id } => {
+                *condition_id
+            }
+            DomainEvent::ConditionDeleted { condition_id } => {
+                self.cancel_for_condition(*condition_id, "The diagnosis was removed").await?;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        match self.conditions.get(condition_id).await? {
+            Some(condition) => self.detect(envelope.tenant_id, &condition).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Detect cases as diagnoses are recorded and changed, across tenants
+    pub fn listen(self: Arc<Self>, bus: &EventBus) -> JoinHandle<()> {
+        let mut events = bus.subscribe();
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            loop {
+                match events.recv().await {
+                    Ok(envelope) => {
+                        if let Err(e) = self.handle_event(&envelope).await {
+                            tracing::error!(
+                                "Failed to detect notifiable disease for {} {}: {}",
+                                envelope.event.name(),
+                                envelope.id,
+                                e
+                            );
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::error!("Disease surveillance fell behind and missed {} events", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+
+    /// Get case report by ID
+    pub async fn get_report(&self, id: Uuid) -> Result<Option<CaseReport>, HimsError> {
+        let row = sqlx::query(GET_CASE_REPORT_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(case_report_from_row))
+    }
+
+    /// The 500 soonest due case reports matching a query
+    pub async fn list_reports(&self, query: &CaseReportQuery) -> Result<Vec<CaseReport>, HimsError> {
+        let rows = sqlx::query(LIST_CASE_REPORTS)
+            .bind(query.status.map(|status| status.as_str()))
+            .bind(query.disease_id)
+            .bind(query.patient_id)
+            .bind(query.overdue.then(Utc::now))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(case_report_from_row).collect())
+    }
+
+    async fn patient(&self, id: Uuid) -> Result<Patient, HimsError> {
+        self.patients
+            .get_patient(id)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| HimsError::InternalError {
+                message: format!("Patient {} does not exist", id),
+            })
+    }
+
+    /// A report's content in a format, from the current diagnosis and
+    /// patient
+    async fn render(&self, report: &CaseReport, format: ReportFormat) -> Result<serde_json::Value, HimsError> {
+        let disease = self
+            .get_disease(report.disease_id)
+            .await?
+            .ok_or_else(|| HimsError::InternalError {
+                message: format!("Notifiable disease {} does not exist", report.disease_id),
+            })?;
+        let condition = self
+            .conditions
+            .get(report.condition_id)
+            .await?
+            .ok_or_else(|| HimsError::ConflictError {
+                message: format!("Condition {} of case report {} was removed", report.condition_id, report.id),
+            })?;
+        let patient = self.patient(report.patient_id).await?;
+
+        Ok(match format {
+            ReportFormat::Fhir => fhir_case_report(report, &disease, condition, patient),
+            ReportFormat::Idsp => idsp_case_report(
+                report,
+                &disease,
+                &condition,
+                &patient,
+                &self.config.reporting_unit,
+                self.config.utc_offset,
+            ),
+        })
+    }
+
+    /// A report's content, in its own format unless another is asked for.
+    /// `None` if there is no such report.
+    pub async fn report_payload(
+        &self,
+        id: Uuid,
+        format: Option<ReportFormat>,
+    ) -> Result<Option<serde_json::Value>, HimsError> {
+        let Some(report) = self.get_report(id).await? else {
+            return Ok(None);
+        };
+        self.render(&report, format.unwrap_or(report.report_format)).await.map(Some)
+    }
+
+    async fn lock_report(conn: &mut PgConnection, id: Uuid) -> Result<Option<CaseReport>, HimsError> {
+        let row = sqlx::query(LOCK_CASE_REPORT)
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(case_report_from_row))
+    }
+
+    async fn save(conn: &mut PgConnection, report: &CaseReport) -> Result<(), HimsError> {
+        sqlx::query(UPDATE_CASE_REPORT)
+            .bind(report.id)
+            .bind(report.status.as_str())
+            .bind(report.submitted_at)
+            .bind(report.submitted_by)
+            .bind(&report.submission_reference)
+            .bind(&report.payload)
+            .bind(&report.response)
+            .bind(report.responded_at)
+            .execute(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(())
+    }
+
+    /// Move a report on by `step`, which fails with a conflict when the
+    /// report is not where the step starts from. `None` if there is no such
+    /// report.
+    async fn progress(
+        &self,
+        id: Uuid,
+        step: impl FnOnce(&mut CaseReport) -> Result<(), String>,
+    ) -> Result<Option<CaseReport>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut report) = Self::lock_report(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        step(&mut report).map_err(|message| HimsError::ConflictError { message })?;
+        Self::save(&mut tx, &report).await?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(Some(report))
+    }
+
+    /// Record a report as submitted to the authority, keeping the payload
+    /// it was submitted with. `None` if there is no such report.
+    pub async fn submit_report(
+        &self,
+        id: Uuid,
+        reference: Option<String>,
+        submitted_by: Option<Uuid>,
+    ) -> Result<Option<CaseReport>, HimsError> {
+        let Some(report) = self.get_report(id).await? else {
+            return Ok(None);
+        };
+        let payload = self.render(&report, report.report_format).await?;
+        let reference = reference.map(|reference| reference.trim().to_string()).filter(|r| !r.is_empty());
+        self.progress(id, |report| report.submit(payload, reference, Utc::now(), submitted_by))
+            .await
+    }
+
+    /// Record the authority acknowledging or rejecting a submission. `None`
+    /// if there is no such report.
+    pub async fn record_response(
+        &self,
+        id: Uuid,
+        accepted: bool,
+        response: Option<String>,
+    ) -> Result<Option<CaseReport>, HimsError> {
+        if !accepted && response.as_deref().is_none_or(|response| response.trim().is_empty()) {
+            return Err(HimsError::ValidationError {
+                message: "A rejection needs the authority's reason".to_string(),
+            });
+        }
+        self.progress(id, |report| report.respond(accepted, response, Utc::now()))
+            .await
+    }
+
+    /// Cancel a report that is not to be submitted. `None` if there is no
+    /// such report.
+    pub async fn cancel_report(&self, id: Uuid, reason: String) -> Result<Option<CaseReport>, HimsError> {
+        if reason.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Cancelling a case report needs a reason".to_string(),
+            });
+        }
+        self.progress(id, |report| report.cancel(reason, Utc::now())).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Coding;
+
+    fn disease() -> NotifiableDisease {
+        NotifiableDisease {
+            id: Uuid::new_v4(),
+            code: "cholera".to_string(),
+            name: "Cholera".to_string(),
+            icd10_codes: vec!["A00".to_string()],
+            snomed_codes: vec!["63650001".to_string()],
+            report_format: ReportFormat::Idsp,
+            deadline_hours: 24,
+            report_suspected: false,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn code(system: &str, code: &str) -> CodeableConcept {
+        CodeableConcept {
+            coding: vec![Coding {
+                system: Some(system.to_string()),
+                version: None,
+                code: Some(code.to_string()),
+                display: None,
+            }],
+            text: None,
+        }
+    }
+
+    fn report(status: CaseReportStatus) -> CaseReport {
+        let now = Utc::now();
+        CaseReport {
+            id: Uuid::new_v4(),
+            disease_id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            condition_id: Uuid::new_v4(),
+            encounter_id: None,
+            status,
+            report_format: ReportFormat::Fhir,
+            detected_at: now,
+            due_at: now + Duration::hours(24),
+            overdue: false,
+            submitted_at: None,
+            submitted_by: None,
+            submission_reference: None,
+            payload: None,
+            response: None,
+            responded_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_disease_matches() {
+        let disease = disease();
+        assert!(disease.matches(&code(ICD_10_SYSTEM, "A00.9")));
+        assert!(disease.matches(&code(ICD_10_CM_SYSTEM, "a009")));
+        assert!(disease.matches(&code(SNOMED_CT_SYSTEM, "63650001")));
+        assert!(!disease.matches(&code(ICD_10_SYSTEM, "A01.0")));
+        assert!(!disease.matches(&code(SNOMED_CT_SYSTEM, "636500011")));
+        assert!(!disease.matches(&code("http://loinc.org", "A00")));
+
+        assert!(disease.reports(&ConditionVerificationStatus::Confirmed));
+        assert!(!disease.reports(&ConditionVerificationStatus::Provisional));
+        assert!(!disease.reports(&ConditionVerificationStatus::Refuted));
+    }
+
+    #[test]
+    fn test_disease_request_check() {
+        let mut request = NotifiableDiseaseRequest {
+            code: " dengue ".to_string(),
+            name: "Dengue".to_string(),
+            icd10_codes: vec!["a97.0".to_string(), "A97".to_string()],
+            snomed_codes: vec![" 38362002 ".to_string()],
+            report_format: ReportFormat::Fhir,
+            deadline_hours: 168,
+            report_suspected: true,
+            active: true,
+        };
+        request.check().unwrap();
+        assert_eq!(request.code, "dengue");
+        assert_eq!(request.icd10_codes, vec!["A970", "A97"]);
+        assert_eq!(request.snomed_codes, vec!["38362002"]);
+
+        request.icd10_codes = vec!["97".to_string()];
+        assert!(request.check().is_err());
+        request.icd10_codes = Vec::new();
+        request.snomed_codes = Vec::new();
+        assert!(request.check().is_err());
+    }
+
+    #[test]
+    fn test_report_workflow() {
+        let now = Utc::now();
+        let mut report = report(CaseReportStatus::Pending);
+        assert!(report.respond(true, None, now).is_err());
+
+        report.submit(serde_json::json!({}), Some("IHIP-1".to_string()), now, None).unwrap();
+        assert_eq!(report.status, CaseReportStatus::Submitted);
+        assert!(report.cancel("Duplicate".to_string(), now).is_err());
+
+        report.respond(false, Some("Missing address".to_string()), now).unwrap();
+        assert_eq!(report.status, CaseReportStatus::Rejected);
+        report.submit(serde_json::json!({}), Some("IHIP-2".to_string()), now, None).unwrap();
+        assert_eq!(report.response, None);
+        report.respond(true, None, now).unwrap();
+        assert_eq!(report.status, CaseReportStatus::Acknowledged);
+        assert!(report.submit(serde_json::json!({}), None, now, None).is_err());
+
+        let mut refuted = self::report(CaseReportStatus::Pending);
+        refuted.cancel("Diagnosis refuted".to_string(), now).unwrap();
+        assert_eq!(refuted.status, CaseReportStatus::Cancelled);
+    }
+}
@@ -0,0 +1,105 @@
+//! Surveillance SQL Queries
+//!
+//! This file contains all SQL queries used by the surveillance service.
+
+/// Add a notifiable disease
+pub const INSERT_DISEASE: &str = r#"
+    INSERT INTO notifiable_diseases (id, code, name, icd10_codes, snomed_codes, report_format, deadline_hours,
+                                     report_suspected, active)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    RETURNING id, code, name, icd10_codes, snomed_codes, report_format, deadline_hours, report_suspected, active,
+              created_at, updated_at
+"#;
+
+/// Get notifiable disease by ID
+pub const GET_DISEASE_BY_ID: &str = r#"
+    SELECT id, code, name, icd10_codes, snomed_codes, report_format, deadline_hours, report_suspected, active,
+           created_at, updated_at
+    FROM notifiable_diseases
+    WHERE id = $1
+"#;
+
+/// All notifiable diseases, by name
+pub const LIST_DISEASES: &str = r#"
+    SELECT id, code, name, icd10_codes, snomed_codes, report_format, deadline_hours, report_suspected, active,
+           created_at, updated_at
+    FROM notifiable_diseases
+    ORDER BY name
+"#;
+
+/// A tenant's active notifiable diseases, for detection across tenants
+pub const GET_TENANT_ACTIVE_DISEASES: &str = r#"
+    SELECT id, code, name, icd10_codes, snomed_codes, report_format, deadline_hours, report_suspected, active,
+           created_at, updated_at
+    FROM notifiable_diseases
+    WHERE tenant_id = $1 AND active
+"#;
+
+/// Replace a notifiable disease
+pub const UPDATE_DISEASE: &str = r#"
+    UPDATE notifiable_diseases
+    SET code = $2, name = $3, icd10_codes = $4, snomed_codes = $5, report_format = $6, deadline_hours = $7,
+        report_suspected = $8, active = $9
+    WHERE id = $1
+    RETURNING id, code, name, icd10_codes, snomed_codes, report_format, deadline_hours, report_suspected, active,
+              created_at, updated_at
+"#;
+
+/// Open a case report for a diagnosis, unless one is open for its disease
+pub const INSERT_CASE_REPORT: &str = r#"
+    INSERT INTO case_reports (id, tenant_id, disease_id, patient_id, condition_id, encounter_id, report_format,
+                              detected_at, due_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (disease_id, condition_id) DO NOTHING
+"#;
+
+/// Get case report by ID
+pub const GET_CASE_REPORT_BY_ID: &str = r#"
+    SELECT id, disease_id, patient_id, condition_id, encounter_id, status, report_format, detected_at, due_at,
+           submitted_at, submitted_by, submission_reference, payload, response, responded_at, created_at,
+           updated_at
+    FROM case_reports
+    WHERE id = $1
+"#;
+
+/// Lock a case report while it moves on
+pub const LOCK_CASE_REPORT: &str = r#"
+    SELECT id, disease_id, patient_id, condition_id, encounter_id, status, report_format, detected_at, due_at,
+           submitted_at, submitted_by, submission_reference, payload, response, responded_at, created_at,
+           updated_at
+    FROM case_reports
+    WHERE id = $1
+    FOR UPDATE
+"#;
+
+/// Case reports, optionally in one status ($1), of one disease ($2) or
+/// patient ($3), or only those still to be submitted and due before $4;
+/// soonest due first
+pub const LIST_CASE_REPORTS: &str = r#"
+    SELECT id, disease_id, patient_id, condition_id, encounter_id, status, report_format, detected_at, due_at,
+           submitted_at, submitted_by, submission_reference, payload, response, responded_at, created_at,
+           updated_at
+    FROM case_reports
+    WHERE ($1::text IS NULL OR status = $1)
+      AND ($2::uuid IS NULL OR disease_id = $2)
+      AND ($3::uuid IS NULL OR patient_id = $3)
+      AND ($4::timestamptz IS NULL OR (status IN ('pending', 'rejected') AND due_at < $4))
+    ORDER BY due_at
+    LIMIT 500
+"#;
+
+/// Save a case report's progress
+pub const UPDATE_CASE_REPORT: &str = r#"
+    UPDATE case_reports
+    SET status = $2, submitted_at = $3, submitted_by = $4, submission_reference = $5, payload = $6,
+        response = $7, responded_at = $8
+    WHERE id = $1
+"#;
+
+/// Cancel the unsubmitted case reports of a diagnosis that was refuted or
+/// removed
+pub const CANCEL_CONDITION_REPORTS: &str = r#"
+    UPDATE case_reports
+    SET status = 'cancelled', response = $2, responded_at = NOW()
+    WHERE condition_id = $1 AND status IN ('pending', 'rejected')
+"#;
@@ -0,0 +1,172 @@
+//! Encryption at rest for the desktop app's local database
+//!
+//! SQLite is built as SQLCipher, which encrypts every page of the database
+//! file, and its journal and WAL, with AES-256. Each install has its own
+//! random 256-bit key, which with the `keychain` feature is kept in the OS
+//! keychain (macOS Keychain, Windows Credential Manager or the Linux Secret
+//! Service) rather than next to the database.
+
+use ring::rand::{SecureRandom, SystemRandom};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use sqlx::{ConnectOptions, Connection, Executor};
+use std::io::Read;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::core::HimsError;
+
+/// First bytes of every unencrypted SQLite database file
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+/// Keychain account the database key is stored under
+#[cfg(feature = "keychain")]
+pub const DATABASE_KEY_ACCOUNT: &str = "database-key";
+
+/// Raw 256-bit SQLCipher key, wiped from memory when dropped
+pub struct DatabaseKey(Zeroizing<[u8; 32]>);
+
+impl DatabaseKey {
+    /// A new random key
+    pub fn generate() -> Result<Self, HimsError> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        SystemRandom::new().fill(key.as_mut()).map_err(|_| HimsError::InternalError {
+            message: "Failed to generate a database key".to_string(),
+        })?;
+        Ok(Self(key))
+    }
+
+    /// A key from the 64 hex digits `to_hex` gives
+    pub fn from_hex(hex: &str) -> Result<Self, HimsError> {
+        let invalid = || HimsError::SecurityError {
+            message: "Database key must be 64 hex digits".to_string(),
+        };
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut key = Zeroizing::new([0u8; 32]);
+        for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let digits = std::str::from_utf8(digits).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(digits, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(key))
+    }
+
+    pub fn to_hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(self.0.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// The key in SQLCipher's raw key syntax, which skips passphrase derivation
+    fn sql_literal(&self) -> String {
+        format!("\"x'{}'\"", self.to_hex().as_str())
+    }
+
+    /// Options that open the database with this key
+    pub(crate) fn apply(&self, options: SqliteConnectOptions) -> SqliteConnectOptions {
+        options.pragma("key", self.sql_literal())
+    }
+
+    /// The key kept in the OS keychain under `service`, generating and
+    /// storing one the first time. Blocks on the keychain, which may ask the
+    /// user to unlock it.
+    #[cfg(feature = "keychain")]
+    pub fn from_keychain(service: &str) -> Result<Self, HimsError> {
+        let keychain = |e: keyring::Error| HimsError::SecurityError {
+            message: format!("OS keychain unavailable for the database key: {}", e),
+        };
+        let entry = keyring::Entry::new(service, DATABASE_KEY_ACCOUNT).map_err(keychain)?;
+        match entry.get_password() {
+            Ok(hex) => Self::from_hex(Zeroizing::new(hex).as_str()),
+            Err(keyring::Error::NoEntry) => {
+                let key = Self::generate()?;
+                entry.set_password(key.to_hex().as_str()).map_err(keychain)?;
+                Ok(key)
+            }
+            Err(e) => Err(keychain(e)),
+        }
+    }
+}
+
+impl std::fmt::Debug for DatabaseKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DatabaseKey(..)")
+    }
+}
+
+/// Encrypt the database `options` point at in place if it is still a plain
+/// SQLite file, such as one written before encryption was turned on
+pub(crate) async fn encrypt_plaintext(options: &SqliteConnectOptions, key: &DatabaseKey) -> Result<(), HimsError> {
+    let path = options.clone().get_filename().into_owned();
+    if !is_plaintext(&path)? {
+        return Ok(());
+    }
+    tracing::info!("Encrypting local database {}", path.display());
+
+    let mut encrypted = path.clone().into_os_string();
+    encrypted.push(".encrypting");
+    let encrypted = Path::new(&encrypted);
+    if encrypted.exists() {
+        std::fs::remove_file(encrypted).map_err(io)?;
+    }
+    let encrypted_name = encrypted.to_str().ok_or_else(|| HimsError::ConfigurationError {
+        message: format!("Database path is not UTF-8: {}", encrypted.display()),
+    })?;
+
+    // Attached databases open with the connection's flags, so it may create
+    let mut conn = options.clone().create_if_missing(true).connect().await.map_err(db)?;
+    let attach = format!(
+        "ATTACH DATABASE '{}' AS encrypted KEY {}",
+        encrypted_name.replace('\'', "''"),
+        key.sql_literal()
+    );
+    conn.execute(Zeroizing::new(attach).as_str()).await.map_err(db)?;
+    conn.execute("SELECT sqlcipher_export('encrypted')").await.map_err(db)?;
+    conn.execute("DETACH DATABASE encrypted").await.map_err(db)?;
+    // Closing the only connection checkpoints and removes the plaintext WAL
+    conn.close().await.map_err(db)?;
+
+    std::fs::rename(encrypted, &path).map_err(io)?;
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = path.clone().into_os_string();
+        sidecar.push(suffix);
+        if Path::new(&sidecar).exists() {
+            std::fs::remove_file(&sidecar).map_err(io)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fail unless the pool is SQLCipher and its key opens the database
+pub(crate) async fn verify(pool: &SqlitePool) -> Result<(), HimsError> {
+    let version: Option<(String,)> = sqlx::query_as("PRAGMA cipher_version").fetch_optional(pool).await.map_err(db)?;
+    if version.is_none() {
+        return Err(HimsError::SecurityError {
+            message: "SQLite is not built with SQLCipher, so the local database cannot be encrypted".to_string(),
+        });
+    }
+    sqlx::query("SELECT count(*) FROM sqlite_master").execute(pool).await.map_err(|e| HimsError::SecurityError {
+        message: format!("Local database key does not open the database: {}", e),
+    })?;
+    Ok(())
+}
+
+fn is_plaintext(path: &Path) -> Result<bool, HimsError> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(io(e)),
+    };
+    let mut header = [0u8; 16];
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header == PLAINTEXT_HEADER),
+        // An empty file is a database SQLite has yet to write
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(io(e)),
+    }
+}
+
+fn io(e: std::io::Error) -> HimsError {
+    HimsError::InternalError { message: e.to_string() }
+}
+
+fn db(e: impl std::fmt::Display) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
@@ -0,0 +1,924 @@
+// src/modules/sync/local_store.rs
+//! The desktop app's own copies of the patients, appointments and medical
+//! records it works with, so the app stays usable offline
+//!
+//! Writes follow the server's rules, are audit logged locally and are queued
+//! in the `SyncOutbox` in the same transaction, for the `SyncEngine` to
+//! replay to the central server. Only resources written on this install are
+//! held; nothing is pulled down from the server.
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{Executor, Sqlite, Transaction};
+use std::collections::HashSet;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::APPOINTMENT_PROFILE;
+use crate::models::{
+    Appointment, AppointmentStatus, AuditAction, AuditEventType, AuditLog, DocumentStatus, MedicalRecord, Patient,
+    ResourceMeta,
+};
+use crate::modules::appointment::appointment_controller::AppointmentCreateRequest;
+use crate::modules::medical_record::medical_record_controller::MedicalRecordCreateRequest;
+use crate::modules::note_template::NoteTemplate;
+use crate::modules::patient::patient_controller::PatientCreateRequest;
+use crate::utils::etag::next_version_id;
+
+#[cfg(feature = "sqlcipher")]
+use super::encryption::{self, DatabaseKey};
+use super::outbox::{ConflictResolution, SyncOperation, SyncOutbox, SyncResourceType};
+use super::sync_sql::sqlite as sql;
+
+/// Most patients a search returns
+const SEARCH_LIMIT: i64 = 50;
+
+/// What the local audit log recorded over a period
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalAuditReport {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub total_events: u64,
+    pub unique_users: u64,
+    /// Reads of patients and their records
+    pub patient_accesses: u64,
+    /// Creates, updates and deletes
+    pub data_modifications: u64,
+    /// Entries whose outcome was not a success
+    pub failed_events: u64,
+    /// Local writes not yet replayed to the central server
+    pub pending_sync: i64,
+    pub entries: Vec<AuditLog>,
+}
+
+/// SQLite-backed store of the desktop app's resources
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    pool: SqlitePool,
+    outbox: SyncOutbox,
+}
+
+impl LocalStore {
+    /// Open (creating if needed) the database at `database_url` and ensure the schema exists
+    pub async fn connect(database_url: &str) -> Result<Self, HimsError> {
+        let options = SqliteConnectOptions::from_str(database_url).map_err(db)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .map_err(db)?;
+        Self::from_pool(pool).await
+    }
+
+    /// Open (creating if needed) the database at `database_url` encrypted
+    /// with `key`, first encrypting it in place if it is still a plain SQLite
+    /// file. Fails when the key does not open it.
+    #[cfg(feature = "sqlcipher")]
+    pub async fn connect_encrypted(database_url: &str, key: &DatabaseKey) -> Result<Self, HimsError> {
+        let options = SqliteConnectOptions::from_str(database_url).map_err(db)?.create_if_missing(true);
+        encryption::encrypt_plaintext(&options, key).await?;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(key.apply(options))
+            .await
+            .map_err(|e| HimsError::SecurityError {
+                message: format!("Failed to open the encrypted local database: {}", e),
+            })?;
+        encryption::verify(&pool).await?;
+        Self::from_pool(pool).await
+    }
+
+    /// Create a store on an existing pool, sharing it with the outbox
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self, HimsError> {
+        pool.execute(sql::CREATE_LOCAL_SCHEMA).await.map_err(db)?;
+        let outbox = SyncOutbox::from_pool(pool.clone()).await.map_err(db)?;
+        Ok(Self { pool, outbox })
+    }
+
+    /// The outbox local writes are queued in, for the `SyncEngine`
+    pub fn outbox(&self) -> &SyncOutbox {
+        &self.outbox
+    }
+
+    /// Register a new patient
+    pub async fn register_patient(&self, request: PatientCreateRequest, user_id: Uuid) -> Result<Patient, HimsError> {
+        if request.name.is_empty() {
+            return Err(validation("A patient needs at least one name"));
+        }
+        let mut patient = Patient::new(request.name, request.telecom, request.gender, request.birth_date);
+        patient.identifier = request.identifier;
+        patient.address = request.address;
+        patient.marital_status = request.marital_status;
+        patient.contact = request.contact;
+        patient.communication = request.communication;
+
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        Self::save_patient(&mut tx, &patient).await?;
+        let audit = AuditLog::new(AuditEventType::Create, AuditAction::Create, "Patient".to_string())
+            .with_user(user_id)
+            .with_patient(patient.id)
+            .with_resource(patient.id);
+        self.commit(tx, audit, SyncResourceType::Patient, SyncOperation::Create, to_json(&patient)?, user_id)
+            .await?;
+
+        tracing::info!("Patient registered locally: {}", patient.id);
+        Ok(patient)
+    }
+
+    /// Get a patient, logging the access
+    pub async fn get_patient(&self, id: Uuid, user_id: Uuid) -> Result<Option<Patient>, HimsError> {
+        let patient: Option<Patient> = load(&self.pool, sql::GET_LOCAL_PATIENT, id).await?;
+        if patient.is_some() {
+            let audit = AuditLog::new(AuditEventType::PatientAccess, AuditAction::Read, "Patient".to_string())
+                .with_user(user_id)
+                .with_patient(id)
+                .with_resource(id);
+            insert_audit(&self.pool, &audit).await?;
+        }
+        Ok(patient)
+    }
+
+    /// Active patients whose names or identifiers contain `query`, most
+    /// recently changed first. Each patient found is an access by the user.
+    pub async fn search_patients(&self, query: &str, user_id: Uuid) -> Result<Vec<Patient>, HimsError> {
+        let rows: Vec<String> = sqlx::query_scalar(sql::SEARCH_LOCAL_PATIENTS)
+            .bind(query.trim().to_lowercase())
+            .bind(SEARCH_LIMIT)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db)?;
+        let patients = rows.iter().map(|row| from_json(row)).collect::<Result<Vec<Patient>, _>>()?;
+
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        for patient in &patients {
+            let audit = AuditLog::new(AuditEventType::PatientAccess, AuditAction::Read, "Patient".to_string())
+                .with_user(user_id)
+                .with_patient(patient.id)
+                .with_resource(patient.id)
+                .with_details("Patient search".to_string());
+            insert_audit(&mut *tx, &audit).await?;
+        }
+        tx.commit().await.map_err(db)?;
+        Ok(patients)
+    }
+
+    /// Replace a patient's details, from the version it was read at
+    pub async fn update_patient(&self, patient: Patient, user_id: Uuid) -> Result<Patient, HimsError> {
+        self.check_no_conflict(SyncResourceType::Patient, patient.id).await?;
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let stored: Patient = require(&mut *tx, sql::GET_LOCAL_PATIENT, "Patient", patient.id).await?;
+        check_version("Patient", patient.id, &stored.meta, &patient.meta)?;
+
+        let mut updated = patient;
+        updated.meta = next_meta(&stored.meta);
+        Self::save_patient(&mut tx, &updated).await?;
+        let audit = AuditLog::new(AuditEventType::Update, AuditAction::Update, "Patient".to_string())
+            .with_user(user_id)
+            .with_patient(updated.id)
+            .with_resource(updated.id);
+        let payload = as_read(to_json(&updated)?, &stored.meta)?;
+        self.commit(tx, audit, SyncResourceType::Patient, SyncOperation::Update, payload, user_id).await?;
+        Ok(updated)
+    }
+
+    /// Book an appointment. Fails with `ConflictError` when a participant
+    /// already holds an overlapping slot.
+    pub async fn book_appointment(
+        &self,
+        request: AppointmentCreateRequest,
+        user_id: Uuid,
+    ) -> Result<Appointment, HimsError> {
+        let mut appointment: Appointment = request.into();
+        let (start, end) = match (appointment.start, appointment.end) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            (Some(_), Some(_)) => return Err(validation("An appointment must end after it starts")),
+            _ => return Err(validation("An appointment needs a start and an end")),
+        };
+        let booked_actors = actors(&appointment);
+        if booked_actors.is_empty() {
+            return Err(validation("An appointment needs at least one participant"));
+        }
+        appointment.status = AppointmentStatus::Booked;
+        appointment.minutes_duration.get_or_insert(((end - start).num_minutes() as u32).max(1));
+        appointment.meta = ResourceMeta {
+            version_id: Some("1".to_string()),
+            last_updated: Utc::now(),
+            profile: vec![APPOINTMENT_PROFILE.to_string()],
+            security: Vec::new(),
+            tag: Vec::new(),
+        };
+
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let overlapping: Vec<String> = sqlx::query_scalar(sql::OVERLAPPING_LOCAL_APPOINTMENTS)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(db)?;
+        for row in overlapping {
+            let other: Appointment = from_json(&row)?;
+            if let Some(actor) = actors(&other).intersection(&booked_actors).next() {
+                return Err(HimsError::ConflictError {
+                    message: format!("{} already has appointment {} at that time", actor, other.id),
+                });
+            }
+        }
+
+        Self::save_appointment(&mut tx, &appointment).await?;
+        let mut audit = AuditLog::new(AuditEventType::Create, AuditAction::Create, "Appointment".to_string())
+            .with_user(user_id)
+            .with_resource(appointment.id);
+        audit.appointment_id = Some(appointment.id.to_string());
+        audit.patient_id = patient_id(&appointment).map(|id| id.to_string());
+        let payload = appointment_payload(to_json(&appointment)?);
+        self.commit(tx, audit, SyncResourceType::Appointment, SyncOperation::Create, payload, user_id)
+            .await?;
+        Ok(appointment)
+    }
+
+    /// Appointments starting in `[from, to)`, of one patient when given
+    pub async fn list_appointments(
+        &self,
+        patient_id: Option<Uuid>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Appointment>, HimsError> {
+        let rows: Vec<String> = sqlx::query_scalar(sql::LIST_LOCAL_APPOINTMENTS)
+            .bind(patient_id.map(|id| id.to_string()))
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db)?;
+        rows.iter().map(|row| from_json(row)).collect()
+    }
+
+    /// Cancel an appointment that has not taken place
+    pub async fn cancel_appointment(&self, id: Uuid, user_id: Uuid) -> Result<Appointment, HimsError> {
+        self.check_no_conflict(SyncResourceType::Appointment, id).await?;
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let stored: Appointment = require(&mut *tx, sql::GET_LOCAL_APPOINTMENT, "Appointment", id).await?;
+        let cancellable = matches!(
+            stored.status,
+            AppointmentStatus::Proposed
+                | AppointmentStatus::Pending
+                | AppointmentStatus::Booked
+                | AppointmentStatus::Waitlist
+        );
+        if !cancellable {
+            return Err(validation(format!("A {} appointment cannot be cancelled", stored.status)));
+        }
+
+        let mut appointment = stored.clone();
+        appointment.status = AppointmentStatus::Cancelled;
+        appointment.meta = next_meta(&stored.meta);
+        Self::save_appointment(&mut tx, &appointment).await?;
+        let mut audit = AuditLog::new(AuditEventType::Update, AuditAction::Update, "Appointment".to_string())
+            .with_user(user_id)
+            .with_resource(id)
+            .with_details("Appointment cancelled".to_string());
+        audit.appointment_id = Some(id.to_string());
+        audit.patient_id = patient_id(&appointment).map(|id| id.to_string());
+        let payload = as_read(appointment_payload(to_json(&appointment)?), &stored.meta)?;
+        self.commit(tx, audit, SyncResourceType::Appointment, SyncOperation::Update, payload, user_id)
+            .await?;
+        Ok(appointment)
+    }
+
+    /// Write a medical record. Only the built-in note templates are known
+    /// offline.
+    pub async fn create_medical_record(
+        &self,
+        request: MedicalRecordCreateRequest,
+        user_id: Uuid,
+    ) -> Result<MedicalRecord, HimsError> {
+        let mut record: MedicalRecord = (&request).into();
+        if let Some(template_id) = &record.template_id {
+            let template = built_in_template(template_id)?;
+            let structured_data = record.structured_data.get_or_insert_with(|| serde_json::json!({}));
+            template.check(structured_data, false)?;
+            if record.content.trim().is_empty() {
+                record.content = template.render(structured_data);
+            }
+        }
+
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        Self::save_medical_record(&mut tx, &record).await?;
+        let audit = AuditLog::new(AuditEventType::Create, AuditAction::Create, "medical-record".to_string())
+            .with_user(user_id)
+            .with_patient(record.patient_id)
+            .with_resource(record.id);
+        self.commit(tx, audit, SyncResourceType::MedicalRecord, SyncOperation::Create, to_json(&record)?, user_id)
+            .await?;
+        Ok(record)
+    }
+
+    /// A patient's medical records, newest first, logging the access
+    pub async fn list_medical_records(&self, patient_id: Uuid, user_id: Uuid) -> Result<Vec<MedicalRecord>, HimsError> {
+        let rows: Vec<String> = sqlx::query_scalar(sql::LIST_LOCAL_MEDICAL_RECORDS)
+            .bind(patient_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db)?;
+        let records = rows.iter().map(|row| from_json(row)).collect::<Result<Vec<MedicalRecord>, _>>()?;
+
+        let audit = AuditLog::new(AuditEventType::PatientAccess, AuditAction::Read, "medical-record".to_string())
+            .with_user(user_id)
+            .with_patient(patient_id)
+            .with_details(format!("{} records listed", records.len()));
+        insert_audit(&self.pool, &audit).await?;
+        Ok(records)
+    }
+
+    /// Finalize a preliminary record, from the version it was read at.
+    /// Every required field of its template must be filled in.
+    pub async fn finalize_medical_record(
+        &self,
+        id: Uuid,
+        version_id: &str,
+        user_id: Uuid,
+    ) -> Result<MedicalRecord, HimsError> {
+        self.check_no_conflict(SyncResourceType::MedicalRecord, id).await?;
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let stored: MedicalRecord = require(&mut *tx, sql::GET_LOCAL_MEDICAL_RECORD, "Medical record", id).await?;
+        if stored.meta.version_id.as_deref() != Some(version_id) {
+            return Err(HimsError::PreconditionFailed {
+                message: format!(
+                    "Record {} is at version {}, not {}",
+                    id,
+                    stored.meta.version_id.as_deref().unwrap_or("none"),
+                    version_id
+                ),
+            });
+        }
+        if !matches!(stored.status, DocumentStatus::Preliminary) {
+            return Err(validation("Only preliminary records can be finalized"));
+        }
+        if let Some(template_id) = &stored.template_id {
+            let data = stored.structured_data.clone().unwrap_or_else(|| serde_json::json!({}));
+            built_in_template(template_id)?.check(&data, true)?;
+        }
+
+        let mut record = stored.clone();
+        record.status = DocumentStatus::Final;
+        record.updated_at = Utc::now();
+        record.meta = next_meta(&stored.meta);
+        Self::save_medical_record(&mut tx, &record).await?;
+        let audit = AuditLog::new(AuditEventType::Update, AuditAction::Update, "medical-record".to_string())
+            .with_user(user_id)
+            .with_patient(record.patient_id)
+            .with_resource(id)
+            .with_details("Medical record finalized".to_string());
+        let payload = as_read(to_json(&record)?, &stored.meta)?;
+        self.commit(tx, audit, SyncResourceType::MedicalRecord, SyncOperation::Update, payload, user_id)
+            .await?;
+        Ok(record)
+    }
+
+    /// Summarize the local audit log for `[start, end)`
+    pub async fn audit_report(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<LocalAuditReport, HimsError> {
+        if start >= end {
+            return Err(validation("The report period must end after it starts"));
+        }
+        let rows: Vec<String> = sqlx::query_scalar(sql::LIST_LOCAL_AUDIT)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db)?;
+        let entries = rows.iter().map(|row| from_json(row)).collect::<Result<Vec<AuditLog>, _>>()?;
+
+        let count = |wanted: fn(&AuditLog) -> bool| entries.iter().filter(|entry| wanted(entry)).count() as u64;
+        Ok(LocalAuditReport {
+            period_start: start,
+            period_end: end,
+            total_events: entries.len() as u64,
+            unique_users: entries.iter().map(|entry| entry.user_id.as_str()).collect::<HashSet<_>>().len() as u64,
+            patient_accesses: count(|entry| matches!(entry.event_type, AuditEventType::PatientAccess)),
+            data_modifications: count(|entry| {
+                matches!(entry.event_type, AuditEventType::Create | AuditEventType::Update | AuditEventType::Delete)
+            }),
+            failed_events: count(|entry| entry.outcome != "success"),
+            pending_sync: self.outbox.pending_count().await.map_err(db)?,
+            entries,
+        })
+    }
+
+    /// Settle a sync conflict and bring this device's copy in line with the
+    /// side kept: the server's state, or the merged payload. Keeping the
+    /// local side leaves the copy as it is.
+    pub async fn resolve_conflict(
+        &self,
+        conflict_id: Uuid,
+        resolution: ConflictResolution,
+        user_id: Uuid,
+    ) -> Result<(), HimsError> {
+        let conflict = self
+            .outbox
+            .get_conflict(conflict_id)
+            .await
+            .map_err(db)?
+            .ok_or_else(|| validation(format!("Sync conflict {} is not on this device", conflict_id)))?;
+        let resource_type = conflict.local_write.resource_type;
+        let id = conflict.local_write.resource_id;
+
+        // `None` keeps the copy, `Some(None)` drops it as deleted on the
+        // server; parsed first so a state the device cannot hold changes nothing
+        let replacement = match &resolution {
+            ConflictResolution::KeepLocal => None,
+            ConflictResolution::KeepServer => Some(
+                conflict
+                    .server_state
+                    .clone()
+                    .map(|state| LocalResource::from_payload(resource_type, state))
+                    .transpose()?,
+            ),
+            ConflictResolution::Merged(payload) => {
+                Some(Some(LocalResource::from_payload(resource_type, payload.clone())?))
+            }
+        };
+        let strategy = match &resolution {
+            ConflictResolution::KeepServer => "keep_server",
+            ConflictResolution::KeepLocal => "keep_local",
+            ConflictResolution::Merged(_) => "merged",
+        };
+
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        match replacement {
+            Some(Some(LocalResource::Patient(patient))) => Self::save_patient(&mut tx, &patient).await?,
+            Some(Some(LocalResource::Appointment(appointment))) => {
+                Self::save_appointment(&mut tx, &appointment).await?
+            }
+            Some(Some(LocalResource::MedicalRecord(record))) => Self::save_medical_record(&mut tx, &record).await?,
+            Some(None) => {
+                let delete = match resource_type {
+                    SyncResourceType::Patient => sql::DELETE_LOCAL_PATIENT,
+                    SyncResourceType::Appointment => sql::DELETE_LOCAL_APPOINTMENT,
+                    SyncResourceType::MedicalRecord => sql::DELETE_LOCAL_MEDICAL_RECORD,
+                };
+                sqlx::query(delete).bind(id.to_string()).execute(&mut *tx).await.map_err(db)?;
+            }
+            None => {}
+        }
+        let audit = AuditLog::new(AuditEventType::Update, AuditAction::Update, resource_type.as_str().to_string())
+            .with_user(user_id)
+            .with_resource(id)
+            .with_details(format!("Sync conflict {} resolved: {}", conflict_id, strategy));
+        insert_audit(&mut *tx, &audit).await?;
+        tx.commit().await.map_err(db)?;
+
+        // After the copy, so a failure leaves the conflict to resolve again
+        self.outbox.resolve_conflict(conflict_id, resolution, user_id).await.map_err(db)?;
+        Ok(())
+    }
+
+    /// Queue the write and log it in the caller's transaction, then commit
+    async fn commit(
+        &self,
+        mut tx: Transaction<'_, Sqlite>,
+        audit: AuditLog,
+        resource_type: SyncResourceType,
+        operation: SyncOperation,
+        payload: Value,
+        user_id: Uuid,
+    ) -> Result<(), HimsError> {
+        insert_audit(&mut *tx, &audit).await?;
+        self.outbox
+            .enqueue_in(&mut tx, resource_type, operation, payload, user_id)
+            .await
+            .map_err(db)?;
+        tx.commit().await.map_err(db)
+    }
+
+    /// Resources with an unresolved sync conflict cannot be edited until
+    /// it is resolved
+    async fn check_no_conflict(&self, resource_type: SyncResourceType, id: Uuid) -> Result<(), HimsError> {
+        if self.outbox.has_conflict(resource_type, id).await.map_err(db)? {
+            return Err(HimsError::ConflictError {
+                message: format!(
+                    "{}/{} has an unresolved sync conflict; resolve it before editing",
+                    resource_type.as_str(),
+                    id
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    async fn save_patient(tx: &mut Transaction<'_, Sqlite>, patient: &Patient) -> Result<(), HimsError> {
+        let mut search_terms: Vec<String> = Vec::new();
+        for name in &patient.name {
+            search_terms.extend(name.text.iter().chain(&name.family).chain(&name.given).cloned());
+        }
+        search_terms.extend(patient.identifier.iter().map(|identifier| identifier.value.clone()));
+
+        sqlx::query(sql::UPSERT_LOCAL_PATIENT)
+            .bind(patient.id.to_string())
+            .bind(search_terms.join(" ").to_lowercase())
+            .bind(patient.active)
+            .bind(to_json(patient)?.to_string())
+            .bind(patient.meta.last_updated)
+            .execute(&mut **tx)
+            .await
+            .map_err(db)?;
+        Ok(())
+    }
+
+    async fn save_appointment(tx: &mut Transaction<'_, Sqlite>, appointment: &Appointment) -> Result<(), HimsError> {
+        sqlx::query(sql::UPSERT_LOCAL_APPOINTMENT)
+            .bind(appointment.id.to_string())
+            .bind(patient_id(appointment).map(|id| id.to_string()))
+            .bind(appointment.status.to_string())
+            .bind(appointment.start)
+            .bind(appointment.end)
+            .bind(to_json(appointment)?.to_string())
+            .execute(&mut **tx)
+            .await
+            .map_err(db)?;
+        Ok(())
+    }
+
+    async fn save_medical_record(tx: &mut Transaction<'_, Sqlite>, record: &MedicalRecord) -> Result<(), HimsError> {
+        let resource = to_json(record)?;
+        sqlx::query(sql::UPSERT_LOCAL_MEDICAL_RECORD)
+            .bind(record.id.to_string())
+            .bind(record.patient_id.to_string())
+            .bind(resource.get("status").and_then(Value::as_str).unwrap_or_default())
+            .bind(record.created_at)
+            .bind(resource.to_string())
+            .execute(&mut **tx)
+            .await
+            .map_err(db)?;
+        Ok(())
+    }
+}
+
+/// A resource as the local tables hold it
+enum LocalResource {
+    Patient(Patient),
+    Appointment(Appointment),
+    MedicalRecord(MedicalRecord),
+}
+
+impl LocalResource {
+    /// Read a sync payload or server row, whose columns are named as on the
+    /// server
+    fn from_payload(resource_type: SyncResourceType, mut payload: Value) -> Result<Self, HimsError> {
+        let invalid = |e: serde_json::Error| validation(format!("Not a valid {}: {}", resource_type.as_str(), e));
+        match resource_type {
+            SyncResourceType::Patient => serde_json::from_value(payload).map(Self::Patient).map_err(invalid),
+            SyncResourceType::Appointment => {
+                if let Some(fields) = payload.as_object_mut() {
+                    for (field, column) in [("start", "start_time"), ("end", "end_time")] {
+                        if let Some(value) = fields.remove(column) {
+                            fields.insert(field.to_string(), value);
+                        }
+                    }
+                }
+                serde_json::from_value(payload).map(Self::Appointment).map_err(invalid)
+            }
+            SyncResourceType::MedicalRecord => {
+                serde_json::from_value(payload).map(Self::MedicalRecord).map_err(invalid)
+            }
+        }
+    }
+}
+
+async fn insert_audit<'e, E>(executor: E, audit: &AuditLog) -> Result<(), HimsError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(sql::INSERT_LOCAL_AUDIT)
+        .bind(&audit.id)
+        .bind(audit.timestamp)
+        .bind(to_json(audit)?.to_string())
+        .execute(executor)
+        .await
+        .map_err(db)?;
+    Ok(())
+}
+
+async fn load<'e, E, T>(executor: E, query: &str, id: Uuid) -> Result<Option<T>, HimsError>
+where
+    E: Executor<'e, Database = Sqlite>,
+    T: DeserializeOwned,
+{
+    let row: Option<String> = sqlx::query_scalar(query)
+        .bind(id.to_string())
+        .fetch_optional(executor)
+        .await
+        .map_err(db)?;
+    row.as_deref().map(from_json).transpose()
+}
+
+async fn require<'e, E, T>(executor: E, query: &str, kind: &str, id: Uuid) -> Result<T, HimsError>
+where
+    E: Executor<'e, Database = Sqlite>,
+    T: DeserializeOwned,
+{
+    load(executor, query, id)
+        .await?
+        .ok_or_else(|| validation(format!("{} {} is not stored on this device", kind, id)))
+}
+
+/// Edits must start from the stored version
+fn check_version(kind: &str, id: Uuid, stored: &ResourceMeta, read: &ResourceMeta) -> Result<(), HimsError> {
+    if stored.version_id != read.version_id {
+        return Err(HimsError::PreconditionFailed {
+            message: format!(
+                "{} {} is at version {}, not {}",
+                kind,
+                id,
+                stored.version_id.as_deref().unwrap_or("none"),
+                read.version_id.as_deref().unwrap_or("none")
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn next_meta(meta: &ResourceMeta) -> ResourceMeta {
+    ResourceMeta {
+        version_id: Some(next_version_id(meta.version_id.as_deref())),
+        last_updated: Utc::now(),
+        ..meta.clone()
+    }
+}
+
+/// The outbox takes updates with the `meta` the edit started from, so the
+/// replay can tell whether the server changed underneath it
+fn as_read(mut payload: Value, read: &ResourceMeta) -> Result<Value, HimsError> {
+    payload["meta"] = to_json(read)?;
+    Ok(payload)
+}
+
+/// Appointment columns on the server are named for their times
+fn appointment_payload(mut payload: Value) -> Value {
+    if let Some(fields) = payload.as_object_mut() {
+        for (field, column) in [("start", "start_time"), ("end", "end_time")] {
+            if let Some(value) = fields.remove(field) {
+                fields.insert(column.to_string(), value);
+            }
+        }
+    }
+    payload
+}
+
+/// References of an appointment's participants, such as `Patient/<id>`
+fn actors(appointment: &Appointment) -> HashSet<String> {
+    appointment
+        .participant
+        .iter()
+        .filter_map(|participant| participant.actor.as_ref())
+        .map(|actor| actor.reference.clone())
+        .collect()
+}
+
+fn patient_id(appointment: &Appointment) -> Option<Uuid> {
+    actors(appointment)
+        .iter()
+        .find_map(|actor| actor.strip_prefix("Patient/").and_then(|id| Uuid::parse_str(id).ok()))
+}
+
+fn built_in_template(template_id: &str) -> Result<NoteTemplate, HimsError> {
+    NoteTemplate::built_in()
+        .into_iter()
+        .find(|template| template.id == template_id)
+        .ok_or_else(|| validation(format!("Note template {} is not available offline", template_id)))
+}
+
+fn to_json(value: &impl Serialize) -> Result<Value, HimsError> {
+    serde_json::to_value(value).map_err(|e| HimsError::InternalError { message: e.to_string() })
+}
+
+fn from_json<T: DeserializeOwned>(row: &str) -> Result<T, HimsError> {
+    serde_json::from_str(row).map_err(|e| HimsError::DatabaseError(format!("Corrupt local row: {}", e)))
+}
+
+fn validation(message: impl Into<String>) -> HimsError {
+    HimsError::ValidationError { message: message.into() }
+}
+
+fn db(e: impl std::fmt::Display) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AppointmentParticipant, Gender, HumanName, ParticipationStatus, Reference};
+    use crate::modules::sync::{SyncConflict, SyncStatus};
+    use chrono::Duration;
+
+    async fn store() -> LocalStore {
+        // One connection, since each in-memory connection is its own database
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        LocalStore::from_pool(pool).await.unwrap()
+    }
+
+    fn registration(family: &str) -> PatientCreateRequest {
+        PatientCreateRequest {
+            identifier: Vec::new(),
+            name: vec![HumanName {
+                use_type: None,
+                text: None,
+                family: Some(family.to_string()),
+                given: vec!["Asha".to_string()],
+                prefix: Vec::new(),
+                suffix: Vec::new(),
+            }],
+            telecom: Vec::new(),
+            gender: Gender::Female,
+            birth_date: None,
+            address: Vec::new(),
+            marital_status: None,
+            contact: Vec::new(),
+            communication: Vec::new(),
+        }
+    }
+
+    fn booking(actors: &[String], start: DateTime<Utc>, minutes: i64) -> AppointmentCreateRequest {
+        AppointmentCreateRequest {
+            service_category: Vec::new(),
+            service_type: Vec::new(),
+            specialty: Vec::new(),
+            appointment_type: None,
+            reason_code: Vec::new(),
+            priority: None,
+            description: None,
+            start: Some(start),
+            end: Some(start + Duration::minutes(minutes)),
+            minutes_duration: None,
+            participant: actors
+                .iter()
+                .map(|actor| AppointmentParticipant {
+                    actor: Some(Reference {
+                        reference: actor.clone(),
+                        display: None,
+                    }),
+                    required: None,
+                    status: ParticipationStatus::Accepted,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_offline_workflow_is_queued_and_audited() {
+        let store = store().await;
+        let user = Uuid::new_v4();
+
+        let patient = store.register_patient(registration("Rao"), user).await.unwrap();
+        assert_eq!(store.search_patients("RAO", user).await.unwrap().len(), 1);
+        assert!(store.get_patient(patient.id, user).await.unwrap().is_some());
+
+        let mut edited = patient.clone();
+        edited.gender = Gender::Other;
+        let edited = store.update_patient(edited, user).await.unwrap();
+        assert_eq!(edited.meta.version_id.as_deref(), Some("2"));
+        // A stale edit is refused
+        assert!(matches!(
+            store.update_patient(patient.clone(), user).await,
+            Err(HimsError::PreconditionFailed { .. })
+        ));
+
+        let record = store
+            .create_medical_record(
+                MedicalRecordCreateRequest {
+                    patient_id: patient.id,
+                    encounter_id: None,
+                    record_type: crate::models::MedicalRecordType::ProgressNote,
+                    content: "Stable".to_string(),
+                    author: Reference {
+                        reference: format!("Practitioner/{}", user),
+                        display: None,
+                    },
+                    template_id: None,
+                    structured_data: None,
+                },
+                user,
+            )
+            .await
+            .unwrap();
+        let record = store.finalize_medical_record(record.id, "1", user).await.unwrap();
+        assert!(matches!(record.status, DocumentStatus::Final));
+        assert_eq!(store.list_medical_records(patient.id, user).await.unwrap().len(), 1);
+
+        // Patient create and update coalesce into one write, plus the record
+        let pending = store.outbox().pending(10).await.unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].operation, SyncOperation::Create);
+        assert_eq!(pending[0].payload["gender"], "other");
+
+        let now = Utc::now();
+        let report = store.audit_report(now - Duration::hours(1), now + Duration::hours(1)).await.unwrap();
+        // The search, the read and the records listing were accesses
+        assert_eq!(report.total_events, 7);
+        assert_eq!(report.data_modifications, 4);
+        assert_eq!(report.patient_accesses, 3);
+        assert_eq!(report.unique_users, 1);
+        assert_eq!(report.pending_sync, 2);
+    }
+
+    #[tokio::test]
+    async fn test_keeping_server_side_of_conflict_updates_copy() {
+        let store = store().await;
+        let user = Uuid::new_v4();
+        let patient = store.register_patient(registration("Rao"), user).await.unwrap();
+
+        let write = store.outbox().pending(1).await.unwrap().remove(0);
+        let mut server_state = write.payload.clone();
+        server_state["gender"] = "other".into();
+        server_state["meta"]["version_id"] = "3".into();
+        let conflict = SyncConflict {
+            id: Uuid::new_v4(),
+            local_write: write,
+            server_version: Some("3".to_string()),
+            server_vector: Default::default(),
+            server_state: Some(server_state),
+            detected_at: Utc::now(),
+        };
+        store.outbox().record_conflict(&conflict).await.unwrap();
+        let status = SyncStatus::read(store.outbox()).await.unwrap();
+        assert_eq!((status.pending, status.conflicts), (0, 1));
+        assert!(status.last_synced_at.is_none());
+
+        store.resolve_conflict(conflict.id, ConflictResolution::KeepServer, user).await.unwrap();
+        let copy = store.get_patient(patient.id, user).await.unwrap().unwrap();
+        assert!(matches!(copy.gender, Gender::Other));
+        assert_eq!(copy.meta.version_id.as_deref(), Some("3"));
+        let status = SyncStatus::read(store.outbox()).await.unwrap();
+        assert_eq!((status.pending, status.conflicts), (0, 0));
+        assert!(matches!(
+            store.resolve_conflict(conflict.id, ConflictResolution::KeepLocal, user).await,
+            Err(HimsError::ValidationError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_bookings_conflict() {
+        let store = store().await;
+        let user = Uuid::new_v4();
+        let patient = format!("Patient/{}", Uuid::new_v4());
+        let doctor = format!("Practitioner/{}", Uuid::new_v4());
+        let start = Utc::now() + Duration::days(1);
+
+        let first = store.book_appointment(booking(&[patient.clone(), doctor.clone()], start, 30), user).await.unwrap();
+        assert!(matches!(first.status, AppointmentStatus::Booked));
+
+        let other_patient = format!("Patient/{}", Uuid::new_v4());
+        let clash = booking(&[other_patient, doctor.clone()], start + Duration::minutes(15), 30);
+        assert!(matches!(store.book_appointment(clash, user).await, Err(HimsError::ConflictError { .. })));
+        // Back to back is fine
+        store.book_appointment(booking(std::slice::from_ref(&doctor), start + Duration::minutes(30), 30), user).await.unwrap();
+
+        store.cancel_appointment(first.id, user).await.unwrap();
+        let rebooked = booking(&[doctor], start + Duration::minutes(15), 15);
+        store.book_appointment(rebooked, user).await.unwrap();
+
+        let listed = store
+            .list_appointments(None, start - Duration::hours(1), start + Duration::hours(2))
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 3);
+        let queued = store.outbox().pending(10).await.unwrap();
+        assert!(queued[0].payload.get("start_time").is_some());
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[tokio::test]
+    async fn test_encrypted_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hims.db");
+        let url = format!("sqlite://{}", path.display());
+        let user = Uuid::new_v4();
+
+        // Written before encryption was turned on
+        let plain = LocalStore::connect(&url).await.unwrap();
+        let patient = plain.register_patient(registration("Rao"), user).await.unwrap();
+        plain.pool.close().await;
+
+        let key = DatabaseKey::generate().unwrap();
+        let store = LocalStore::connect_encrypted(&url, &key).await.unwrap();
+        assert_eq!(store.get_patient(patient.id, user).await.unwrap().unwrap().id, patient.id);
+        store.pool.close().await;
+        let file = std::fs::read(&path).unwrap();
+        assert!(!file.starts_with(b"SQLite format 3"));
+        assert!(!file.windows(3).any(|window| window == b"Rao"));
+
+        let reopened = DatabaseKey::from_hex(&key.to_hex()).unwrap();
+        let store = LocalStore::connect_encrypted(&url, &reopened).await.unwrap();
+        assert_eq!(store.search_patients("rao", user).await.unwrap().len(), 1);
+        store.pool.close().await;
+
+        let wrong = DatabaseKey::generate().unwrap();
+        assert!(matches!(
+            LocalStore::connect_encrypted(&url, &wrong).await,
+            Err(HimsError::SecurityError { .. })
+        ));
+    }
+}
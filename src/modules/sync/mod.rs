@@ -0,0 +1,30 @@
+// src/modules/sync/mod.rs
+//! Offline Sync Module
+//!
+//! Lets the desktop app keep working without a connection to the central
+//! server. It provides:
+//!
+//! - A SQLite outbox of local patient, appointment and medical record writes
+//! - Replay to the central PostgreSQL server when connectivity returns
+//! - Conflict detection via `ResourceMeta` version IDs and version vectors
+//! - A conflict-resolution API (keep server, keep local, or merge)
+//! - Sync status and progress updates for the app to show
+//! - A local store of the app's patients, appointments and medical records,
+//!   whose writes are audit logged and queued in the outbox
+//! - Encryption of the local database at rest with SQLCipher, keyed from the
+//!   OS keychain (`sqlcipher` and `keychain` features)
+
+pub mod version;
+pub mod outbox;
+pub mod replay;
+pub mod local_store;
+pub mod sync_sql;
+#[cfg(feature = "sqlcipher")]
+pub mod encryption;
+
+pub use version::*;
+pub use outbox::*;
+pub use replay::*;
+pub use local_store::*;
+#[cfg(feature = "sqlcipher")]
+pub use encryption::*;
@@ -0,0 +1,668 @@
+// src/modules/sync/outbox.rs
+//! Local write queue for offline use
+//!
+//! Writes made while the desktop app is offline are queued in its SQLite
+//! database, one pending write per resource, and replayed in order by the
+//! `SyncEngine`. Writes the server rejected as conflicting wait here until
+//! resolved.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Executor;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::sync_sql::{postgres, sqlite as sql};
+use super::version::VersionVector;
+
+type PendingRow = (String, String, String, String, String, String, Option<String>, String, DateTime<Utc>, i64, Option<String>);
+type ConflictRow = (String, String, Option<String>, String, Option<String>, DateTime<Utc>);
+
+/// Key in `sync_state` holding this install's node ID
+const NODE_ID_KEY: &str = "node_id";
+/// Key in `sync_state` holding when a sync pass last finished against the server
+const LAST_SYNCED_KEY: &str = "last_synced_at";
+
+/// Resources that can be edited offline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SyncResourceType {
+    Patient,
+    Appointment,
+    MedicalRecord,
+}
+
+impl SyncResourceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncResourceType::Patient => "Patient",
+            SyncResourceType::Appointment => "Appointment",
+            SyncResourceType::MedicalRecord => "MedicalRecord",
+        }
+    }
+
+    /// FHIR type the server serves the resource as
+    pub fn fhir_type(&self) -> &'static str {
+        match self {
+            SyncResourceType::Patient => "Patient",
+            SyncResourceType::Appointment => "Appointment",
+            SyncResourceType::MedicalRecord => "DocumentReference",
+        }
+    }
+
+    /// Table on the central server
+    pub fn table(&self) -> &'static str {
+        match self {
+            SyncResourceType::Patient => "patients",
+            SyncResourceType::Appointment => "appointments",
+            SyncResourceType::MedicalRecord => "medical_records",
+        }
+    }
+
+    /// Columns a queued payload may set; other keys are ignored
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            SyncResourceType::Patient => &[
+                "id", "active", "name", "telecom", "gender", "birth_date", "deceased", "address",
+                "marital_status", "contact", "communication", "managing_organization", "meta",
+            ],
+            SyncResourceType::Appointment => &[
+                "id", "status", "service_category", "service_type", "specialty", "appointment_type",
+                "reason_code", "priority", "description", "start_time", "end_time", "minutes_duration",
+                "participant", "meta",
+            ],
+            SyncResourceType::MedicalRecord => &[
+                "id", "patient_id", "encounter_id", "record_type", "status", "subject", "author",
+                "content", "created_at", "updated_at", "meta", "template_id", "structured_data",
+            ],
+        }
+    }
+
+    /// Soft delete, matching how each service deletes
+    pub fn soft_delete_sql(&self) -> &'static str {
+        match self {
+            SyncResourceType::Patient => postgres::SOFT_DELETE_PATIENT,
+            SyncResourceType::Appointment => postgres::SOFT_DELETE_APPOINTMENT,
+            SyncResourceType::MedicalRecord => postgres::SOFT_DELETE_MEDICAL_RECORD,
+        }
+    }
+}
+
+impl FromStr for SyncResourceType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "Patient" => Ok(SyncResourceType::Patient),
+            "Appointment" => Ok(SyncResourceType::Appointment),
+            "MedicalRecord" => Ok(SyncResourceType::MedicalRecord),
+            other => Err(anyhow!("Unknown sync resource type: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+impl SyncOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncOperation::Create => "create",
+            SyncOperation::Update => "update",
+            SyncOperation::Delete => "delete",
+        }
+    }
+
+    /// The single write equivalent to `self` followed by `next`, or `None`
+    /// when they cancel out (a resource created and deleted offline)
+    fn coalesce(self, next: SyncOperation) -> Option<SyncOperation> {
+        match (self, next) {
+            (SyncOperation::Create, SyncOperation::Delete) => None,
+            (SyncOperation::Create, _) => Some(SyncOperation::Create),
+            (_, next) => Some(next),
+        }
+    }
+}
+
+impl FromStr for SyncOperation {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "create" => Ok(SyncOperation::Create),
+            "update" => Ok(SyncOperation::Update),
+            "delete" => Ok(SyncOperation::Delete),
+            other => Err(anyhow!("Unknown sync operation: {}", other)),
+        }
+    }
+}
+
+/// A local write waiting to be replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingWrite {
+    pub id: Uuid,
+    pub resource_type: SyncResourceType,
+    pub resource_id: Uuid,
+    pub operation: SyncOperation,
+    /// User who made the write; the last one when several were coalesced
+    pub user_id: Uuid,
+    /// The resource's columns as JSON, e.g. `name`, `gender`, `meta`
+    pub payload: Value,
+    /// `ResourceMeta.versionId` the local edit started from; `None` for creates
+    pub base_version: Option<String>,
+    pub version_vector: VersionVector,
+    pub queued_at: DateTime<Utc>,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+}
+
+/// A write the server could not take because the resource changed there
+/// since the local edit started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub id: Uuid,
+    pub local_write: PendingWrite,
+    /// `None` when the resource no longer exists on the server
+    pub server_version: Option<String>,
+    pub server_vector: VersionVector,
+    pub server_state: Option<Value>,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// How to settle a conflict
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "strategy", content = "payload", rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Drop the local write; the caller refreshes from `server_state`
+    KeepServer,
+    /// Replay the local write over the server's version
+    KeepLocal,
+    /// Replay a caller-merged payload over the server's version
+    Merged(Value),
+}
+
+/// SQLite-backed outbox of offline writes
+#[derive(Debug, Clone)]
+pub struct SyncOutbox {
+    pool: SqlitePool,
+    node_id: String,
+}
+
+impl SyncOutbox {
+    /// Open (creating if needed) the database at `database_url` and ensure the schema exists
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .context("Failed to open sync database")?;
+        Self::from_pool(pool).await
+    }
+
+    /// Create an outbox on an existing pool, e.g. the one used by the
+    /// SQLite authorization storage
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
+        pool.execute(sql::CREATE_SCHEMA)
+            .await
+            .context("Failed to create sync schema")?;
+
+        // Generated once per install and kept for its version vector entries
+        sqlx::query(sql::SET_STATE)
+            .bind(NODE_ID_KEY)
+            .bind(format!("node-{}", Uuid::new_v4()))
+            .execute(&pool)
+            .await?;
+        let node_id: String = sqlx::query_scalar(sql::GET_STATE)
+            .bind(NODE_ID_KEY)
+            .fetch_one(&pool)
+            .await?;
+
+        Ok(Self { pool, node_id })
+    }
+
+    /// This install's name in version vectors
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Queue a local write. `resource` is the full resource as JSON with its
+    /// `id` and the `meta` it was read with, so the replay can tell whether
+    /// the server changed underneath it. Later writes to a resource that is
+    /// already queued replace the earlier one; `None` means the write
+    /// cancelled out the queued one. `user_id` is the user making the write,
+    /// whom the server records it against when it is replayed.
+    pub async fn enqueue(
+        &self,
+        resource_type: SyncResourceType,
+        operation: SyncOperation,
+        resource: Value,
+        user_id: Uuid,
+    ) -> Result<Option<PendingWrite>> {
+        let mut tx = self.pool.begin().await?;
+        let write = self.enqueue_in(&mut tx, resource_type, operation, resource, user_id).await?;
+        tx.commit().await?;
+        Ok(write)
+    }
+
+    /// [`Self::enqueue`] within a transaction on the outbox's pool, so the
+    /// write is queued only if the caller's own changes commit
+    pub async fn enqueue_in(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        resource_type: SyncResourceType,
+        operation: SyncOperation,
+        resource: Value,
+        user_id: Uuid,
+    ) -> Result<Option<PendingWrite>> {
+        let resource_id = resource
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Queued {} has no id", resource_type.as_str()))
+            .and_then(|id| Uuid::parse_str(id).context("Queued resource id is not a UUID"))?;
+
+        if self.has_conflict_in(&mut **tx, resource_type, resource_id).await? {
+            bail!(
+                "{}/{} has an unresolved sync conflict; resolve it before editing",
+                resource_type.as_str(),
+                resource_id
+            );
+        }
+
+        let existing: Option<PendingRow> = sqlx::query_as(&sql::get_pending_for_resource())
+            .bind(resource_type.as_str())
+            .bind(resource_id.to_string())
+            .fetch_optional(&mut **tx)
+            .await?;
+
+        let now = Utc::now();
+        let write = match existing.map(Self::row_to_write).transpose()? {
+            Some(mut previous) => {
+                let Some(operation) = previous.operation.coalesce(operation) else {
+                    sqlx::query(sql::DELETE_PENDING)
+                        .bind(previous.id.to_string())
+                        .execute(&mut **tx)
+                        .await?;
+                    return Ok(None);
+                };
+                previous.operation = operation;
+                previous.user_id = user_id;
+                previous.payload = resource;
+                previous.version_vector.increment(&self.node_id);
+                previous.queued_at = now;
+                previous.attempts = 0;
+                previous.last_error = None;
+
+                sqlx::query(sql::UPDATE_PENDING)
+                    .bind(previous.id.to_string())
+                    .bind(previous.operation.as_str())
+                    .bind(previous.user_id.to_string())
+                    .bind(previous.payload.to_string())
+                    .bind(serde_json::to_string(&previous.version_vector)?)
+                    .bind(previous.queued_at)
+                    .execute(&mut **tx)
+                    .await?;
+                previous
+            }
+            None => {
+                let meta = resource.get("meta");
+                let base_version = match operation {
+                    SyncOperation::Create => None,
+                    _ => meta
+                        .and_then(|meta| meta.get("version_id"))
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                };
+                let mut version_vector: VersionVector = meta
+                    .and_then(|meta| meta.get("version_vector"))
+                    .map(|vector| serde_json::from_value(vector.clone()))
+                    .transpose()?
+                    .unwrap_or_default();
+                version_vector.increment(&self.node_id);
+
+                let write = PendingWrite {
+                    id: Uuid::new_v4(),
+                    resource_type,
+                    resource_id,
+                    operation,
+                    user_id,
+                    payload: resource,
+                    base_version,
+                    version_vector,
+                    queued_at: now,
+                    attempts: 0,
+                    last_error: None,
+                };
+                self.insert_pending(tx, &write).await?;
+                write
+            }
+        };
+
+        Ok(Some(write))
+    }
+
+    /// Whether a resource has an unresolved conflict, which blocks editing it
+    pub async fn has_conflict(&self, resource_type: SyncResourceType, resource_id: Uuid) -> Result<bool> {
+        self.has_conflict_in(&self.pool, resource_type, resource_id).await
+    }
+
+    async fn has_conflict_in<'e, E>(
+        &self,
+        executor: E,
+        resource_type: SyncResourceType,
+        resource_id: Uuid,
+    ) -> Result<bool>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let conflicted = sqlx::query_scalar(sql::HAS_CONFLICT)
+            .bind(resource_type.as_str())
+            .bind(resource_id.to_string())
+            .fetch_one(executor)
+            .await?;
+        Ok(conflicted)
+    }
+
+    /// Queued writes, oldest first
+    pub async fn pending(&self, limit: i64) -> Result<Vec<PendingWrite>> {
+        let rows: Vec<PendingRow> = sqlx::query_as(&sql::list_pending())
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter().map(Self::row_to_write).collect()
+    }
+
+    pub async fn pending_count(&self) -> Result<i64> {
+        Ok(sqlx::query_scalar(sql::COUNT_PENDING).fetch_one(&self.pool).await?)
+    }
+
+    /// Conflicts waiting to be resolved
+    pub async fn conflict_count(&self) -> Result<i64> {
+        Ok(sqlx::query_scalar(sql::COUNT_CONFLICTS).fetch_one(&self.pool).await?)
+    }
+
+    /// When a sync pass last finished against the server, kept across restarts
+    pub async fn last_synced_at(&self) -> Result<Option<DateTime<Utc>>> {
+        let value: Option<String> = sqlx::query_scalar(sql::GET_STATE)
+            .bind(LAST_SYNCED_KEY)
+            .fetch_optional(&self.pool)
+            .await?;
+        value
+            .map(|value| Ok(DateTime::parse_from_rfc3339(&value)?.with_timezone(&Utc)))
+            .transpose()
+    }
+
+    pub async fn mark_synced(&self, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(sql::PUT_STATE)
+            .bind(LAST_SYNCED_KEY)
+            .bind(at.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove a write the server has taken
+    pub async fn complete(&self, write_id: Uuid) -> Result<()> {
+        sqlx::query(sql::DELETE_PENDING)
+            .bind(write_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Keep a write queued after the server rejected it for a reason other
+    /// than a conflict
+    pub async fn record_failure(&self, write_id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(sql::RECORD_FAILURE)
+            .bind(write_id.to_string())
+            .bind(error)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Move a write from the queue to the conflict list
+    pub async fn record_conflict(&self, conflict: &SyncConflict) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(sql::DELETE_PENDING)
+            .bind(conflict.local_write.id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(sql::INSERT_CONFLICT)
+            .bind(conflict.id.to_string())
+            .bind(conflict.local_write.resource_type.as_str())
+            .bind(conflict.local_write.resource_id.to_string())
+            .bind(serde_json::to_string(&conflict.local_write)?)
+            .bind(&conflict.server_version)
+            .bind(serde_json::to_string(&conflict.server_vector)?)
+            .bind(conflict.server_state.as_ref().map(Value::to_string))
+            .bind(conflict.detected_at)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Unresolved conflicts, oldest first
+    pub async fn conflicts(&self) -> Result<Vec<SyncConflict>> {
+        let rows: Vec<ConflictRow> = sqlx::query_as(sql::LIST_CONFLICTS)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter().map(Self::row_to_conflict).collect()
+    }
+
+    pub async fn get_conflict(&self, conflict_id: Uuid) -> Result<Option<SyncConflict>> {
+        let row: Option<ConflictRow> = sqlx::query_as(sql::GET_CONFLICT)
+            .bind(conflict_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(Self::row_to_conflict).transpose()
+    }
+
+    /// Settle a conflict. Keeping the local side (as is or merged) queues it
+    /// again on top of the server's current version, so the next replay
+    /// overwrites the server unless it changed yet again, as a write of
+    /// `user_id`, who settled it. Returns the re-queued write, if any.
+    pub async fn resolve_conflict(
+        &self,
+        conflict_id: Uuid,
+        resolution: ConflictResolution,
+        user_id: Uuid,
+    ) -> Result<Option<PendingWrite>> {
+        let conflict = self
+            .get_conflict(conflict_id)
+            .await?
+            .ok_or_else(|| anyhow!("Sync conflict not found: {}", conflict_id))?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(sql::DELETE_CONFLICT)
+            .bind(conflict_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        let payload = match resolution {
+            ConflictResolution::KeepServer => None,
+            ConflictResolution::KeepLocal => Some(conflict.local_write.payload.clone()),
+            ConflictResolution::Merged(payload) => Some(payload),
+        };
+        let server_exists = conflict.server_state.is_some();
+        let operation = match (conflict.local_write.operation, server_exists) {
+            // Already gone on the server
+            (SyncOperation::Delete, false) => None,
+            (SyncOperation::Delete, true) => Some(SyncOperation::Delete),
+            (_, true) => Some(SyncOperation::Update),
+            (_, false) => Some(SyncOperation::Create),
+        };
+
+        let requeued = match (payload, operation) {
+            (Some(payload), Some(operation)) => {
+                let mut version_vector = conflict.local_write.version_vector.clone();
+                version_vector.merge(&conflict.server_vector);
+                version_vector.increment(&self.node_id);
+
+                let write = PendingWrite {
+                    id: Uuid::new_v4(),
+                    operation,
+                    user_id,
+                    payload,
+                    base_version: conflict.server_version.clone(),
+                    version_vector,
+                    queued_at: Utc::now(),
+                    attempts: 0,
+                    last_error: None,
+                    ..conflict.local_write
+                };
+                self.insert_pending(&mut tx, &write).await?;
+                Some(write)
+            }
+            _ => None,
+        };
+
+        tx.commit().await?;
+        tracing::info!(
+            "Resolved sync conflict {} for {}/{}",
+            conflict_id,
+            conflict.local_write.resource_type.as_str(),
+            conflict.local_write.resource_id
+        );
+        Ok(requeued)
+    }
+
+    async fn insert_pending(&self, tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, write: &PendingWrite) -> Result<()> {
+        sqlx::query(sql::INSERT_PENDING)
+            .bind(write.id.to_string())
+            .bind(write.resource_type.as_str())
+            .bind(write.resource_id.to_string())
+            .bind(write.operation.as_str())
+            .bind(write.user_id.to_string())
+            .bind(write.payload.to_string())
+            .bind(&write.base_version)
+            .bind(serde_json::to_string(&write.version_vector)?)
+            .bind(write.queued_at)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    fn row_to_write(row: PendingRow) -> Result<PendingWrite> {
+        let (id, resource_type, resource_id, operation, user_id, payload, base_version, version_vector, queued_at, attempts, last_error) =
+            row;
+        Ok(PendingWrite {
+            id: Uuid::parse_str(&id)?,
+            resource_type: resource_type.parse()?,
+            resource_id: Uuid::parse_str(&resource_id)?,
+            operation: operation.parse()?,
+            user_id: Uuid::parse_str(&user_id)?,
+            payload: serde_json::from_str(&payload)?,
+            base_version,
+            version_vector: serde_json::from_str(&version_vector).unwrap_or_default(),
+            queued_at,
+            attempts,
+            last_error,
+        })
+    }
+
+    fn row_to_conflict(row: ConflictRow) -> Result<SyncConflict> {
+        let (id, local_write, server_version, server_vector, server_state, detected_at) = row;
+        Ok(SyncConflict {
+            id: Uuid::parse_str(&id)?,
+            local_write: serde_json::from_str(&local_write)?,
+            server_version,
+            server_vector: serde_json::from_str(&server_vector).unwrap_or_default(),
+            server_state: server_state.map(|state| serde_json::from_str(&state)).transpose()?,
+            detected_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn outbox() -> SyncOutbox {
+        // One connection, since each in-memory connection is its own database
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        SyncOutbox::from_pool(pool).await.unwrap()
+    }
+
+    fn patient(id: Uuid, version: &str, family: &str) -> Value {
+        json!({
+            "id": id.to_string(),
+            "name": [{ "family": family }],
+            "meta": { "version_id": version, "version_vector": {} }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_writes_to_one_resource_coalesce() {
+        let outbox = outbox().await;
+        let id = Uuid::new_v4();
+        let (user, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let first = outbox.enqueue(SyncResourceType::Patient, SyncOperation::Update, patient(id, "3", "Doe"), user).await.unwrap().unwrap();
+        let second = outbox.enqueue(SyncResourceType::Patient, SyncOperation::Update, patient(id, "3", "Roe"), other).await.unwrap().unwrap();
+
+        assert_eq!(first.id, second.id);
+        assert_eq!(second.base_version.as_deref(), Some("3"));
+        assert_eq!(second.version_vector.get(outbox.node_id()), 2);
+        assert_eq!(second.user_id, other);
+
+        let pending = outbox.pending(10).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].payload["name"][0]["family"], "Roe");
+    }
+
+    #[tokio::test]
+    async fn test_create_then_delete_cancels_out() {
+        let outbox = outbox().await;
+        let id = Uuid::new_v4();
+        let user = Uuid::new_v4();
+
+        outbox.enqueue(SyncResourceType::Patient, SyncOperation::Create, patient(id, "1", "Doe"), user).await.unwrap();
+        outbox.enqueue(SyncResourceType::Patient, SyncOperation::Delete, patient(id, "1", "Doe"), user).await.unwrap();
+
+        assert_eq!(outbox.pending_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_keep_local_requeues_on_server_version() {
+        let outbox = outbox().await;
+        let id = Uuid::new_v4();
+        let (user, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let write = outbox.enqueue(SyncResourceType::Patient, SyncOperation::Update, patient(id, "3", "Doe"), user).await.unwrap().unwrap();
+
+        let conflict = SyncConflict {
+            id: Uuid::new_v4(),
+            local_write: write,
+            server_version: Some("4".to_string()),
+            server_vector: VersionVector::new(),
+            server_state: Some(patient(id, "4", "Smith")),
+            detected_at: Utc::now(),
+        };
+        outbox.record_conflict(&conflict).await.unwrap();
+        assert_eq!(outbox.pending_count().await.unwrap(), 0);
+        assert!(outbox
+            .enqueue(SyncResourceType::Patient, SyncOperation::Update, patient(id, "3", "Poe"), user)
+            .await
+            .is_err());
+
+        let requeued = outbox
+            .resolve_conflict(conflict.id, ConflictResolution::KeepLocal, other)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(requeued.base_version.as_deref(), Some("4"));
+        assert_eq!(requeued.operation, SyncOperation::Update);
+        assert_eq!(requeued.user_id, other);
+        assert!(outbox.conflicts().await.unwrap().is_empty());
+        assert_eq!(outbox.pending_count().await.unwrap(), 1);
+    }
+}
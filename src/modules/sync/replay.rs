@@ -0,0 +1,471 @@
+// src/modules/sync/replay.rs
+//! Replays queued offline writes to the central PostgreSQL server
+//!
+//! A write applies only if the server still has the version the local edit
+//! started from (`ResourceMeta.version_id`); otherwise it becomes a
+//! conflict. Version vectors recognise writes the server already has, e.g.
+//! when the app stopped between applying a write and removing it from the
+//! queue. Applied writes are audit logged and versioned as changes of the
+//! user who made them offline.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::outbox::{PendingWrite, SyncConflict, SyncOperation, SyncOutbox, SyncResourceType};
+use super::sync_sql::postgres as sql;
+use super::version::VersionVector;
+use crate::models::{AuditAction, AuditEventType, AuditLog};
+use crate::modules::audit::AuditService;
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::utils::etag::next_version_id;
+
+/// Writes replayed per batch
+const BATCH_SIZE: i64 = 100;
+
+/// Result of replaying one write
+#[derive(Debug, Clone)]
+pub enum ReplayOutcome {
+    Applied { version: String },
+    /// The server's version vector already includes the write
+    AlreadyApplied,
+    Conflict(Box<SyncConflict>),
+}
+
+/// What one sync pass did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncReport {
+    pub applied: usize,
+    pub already_applied: usize,
+    pub conflicts: usize,
+    pub failed: usize,
+    /// The pass stopped because the server was unreachable
+    pub offline: bool,
+    pub remaining: i64,
+}
+
+/// How far the running sync pass has got
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SyncProgress {
+    /// Writes replayed so far, whatever their outcome
+    pub completed: usize,
+    /// Writes queued when the pass started
+    pub total: i64,
+}
+
+/// What the app shows about syncing: whether it is running, when it last
+/// reached the server and what still waits
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncStatus {
+    /// Set while a pass runs
+    pub progress: Option<SyncProgress>,
+    /// Whether the last pass reached the server; `None` before the first
+    pub online: Option<bool>,
+    /// When a pass last finished against the server, or with nothing to send
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_report: Option<SyncReport>,
+    /// Why the last pass failed, if it did
+    pub last_error: Option<String>,
+    pub pending: i64,
+    /// Conflicts waiting to be resolved
+    pub conflicts: i64,
+}
+
+impl SyncStatus {
+    /// Status of an outbox no engine has run against yet, such as when no
+    /// server is configured
+    pub async fn read(outbox: &SyncOutbox) -> Result<Self> {
+        Ok(Self {
+            last_synced_at: outbox.last_synced_at().await?,
+            pending: outbox.pending_count().await?,
+            conflicts: outbox.conflict_count().await?,
+            ..Self::default()
+        })
+    }
+}
+
+/// Offline sync engine
+pub struct SyncEngine {
+    outbox: SyncOutbox,
+    server: PgPool,
+    events: EventBus,
+    status: watch::Sender<SyncStatus>,
+    /// Held by the running pass, so manual and background passes never overlap
+    pass: Mutex<()>,
+}
+
+impl SyncEngine {
+    /// `server` is typically created with `connect_lazy`, so the engine can
+    /// start while offline
+    pub fn new(outbox: SyncOutbox, server: PgPool) -> Self {
+        Self::with_events(outbox, server, EventBus::default())
+    }
+
+    /// Create the engine publishing conflicts to the app's event bus
+    pub fn with_events(outbox: SyncOutbox, server: PgPool, events: EventBus) -> Self {
+        Self {
+            outbox,
+            server,
+            events,
+            status: watch::channel(SyncStatus::default()).0,
+            pass: Mutex::new(()),
+        }
+    }
+
+    /// Create the engine for the server at `server_url` without connecting,
+    /// so it starts while offline
+    pub fn connect_lazy(outbox: SyncOutbox, server_url: &str) -> Result<Self> {
+        let server = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(Duration::from_secs(10))
+            .connect_lazy(server_url)
+            .context("Invalid sync server URL")?;
+        Ok(Self::new(outbox, server))
+    }
+
+    /// The local queue, for enqueueing writes and resolving conflicts
+    pub fn outbox(&self) -> &SyncOutbox {
+        &self.outbox
+    }
+
+    /// The central server's pool, for checking logins against it
+    pub fn server(&self) -> &PgPool {
+        &self.server
+    }
+
+    /// Current status, with the queue's counts read now
+    pub async fn status(&self) -> Result<SyncStatus> {
+        self.refresh_status().await?;
+        Ok(self.status.borrow().clone())
+    }
+
+    /// Status updates: as each write of a pass is replayed, when a pass ends
+    /// and when `refresh_status` finds the queue changed
+    pub fn subscribe(&self) -> watch::Receiver<SyncStatus> {
+        self.status.subscribe()
+    }
+
+    /// Re-read the queue's counts, notifying subscribers if they changed,
+    /// e.g. after a local write or a resolved conflict
+    pub async fn refresh_status(&self) -> Result<()> {
+        let current = SyncStatus::read(&self.outbox).await?;
+        self.status.send_if_modified(|status| {
+            let changed = (status.pending, status.conflicts, status.last_synced_at)
+                != (current.pending, current.conflicts, current.last_synced_at);
+            status.pending = current.pending;
+            status.conflicts = current.conflicts;
+            status.last_synced_at = current.last_synced_at;
+            changed
+        });
+        Ok(())
+    }
+
+    /// Replay queued writes in order until the queue is empty or the server
+    /// becomes unreachable. Waits for a pass already running to finish.
+    pub async fn sync_once(&self) -> Result<SyncReport> {
+        let _pass = self.pass.lock().await;
+        let started = Utc::now();
+        let total = self.outbox.pending_count().await?;
+        self.status.send_modify(|status| {
+            status.progress = Some(SyncProgress { completed: 0, total });
+            status.last_attempt_at = Some(started);
+        });
+
+        let result = self.replay_queue().await;
+        if matches!(&result, Ok(report) if !report.offline) {
+            if let Err(e) = self.outbox.mark_synced(Utc::now()).await {
+                tracing::error!("Failed to record sync time: {:#}", e);
+            }
+        }
+        self.status.send_modify(|status| {
+            status.progress = None;
+            match &result {
+                Ok(report) => {
+                    status.online = Some(!report.offline);
+                    status.last_report = Some(report.clone());
+                    status.last_error = None;
+                }
+                Err(e) => status.last_error = Some(format!("{:#}", e)),
+            }
+        });
+        self.refresh_status().await?;
+        result
+    }
+
+    async fn replay_queue(&self) -> Result<SyncReport> {
+        let mut report = SyncReport::default();
+        let mut failed: Vec<Uuid> = Vec::new();
+
+        'batches: loop {
+            let batch: Vec<PendingWrite> = self
+                .outbox
+                .pending(BATCH_SIZE + failed.len() as i64)
+                .await?
+                .into_iter()
+                .filter(|write| !failed.contains(&write.id))
+                .collect();
+            if batch.is_empty() {
+                break;
+            }
+
+            for write in batch {
+                match self.replay(&write).await {
+                    Ok(ReplayOutcome::Applied { version }) => {
+                        tracing::debug!(
+                            "Synced {}/{} at version {}",
+                            write.resource_type.as_str(),
+                            write.resource_id,
+                            version
+                        );
+                        self.outbox.complete(write.id).await?;
+                        report.applied += 1;
+                    }
+                    Ok(ReplayOutcome::AlreadyApplied) => {
+                        self.outbox.complete(write.id).await?;
+                        report.already_applied += 1;
+                    }
+                    Ok(ReplayOutcome::Conflict(conflict)) => {
+                        tracing::warn!(
+                            "Sync conflict on {}/{}: local edit of version {:?}, server at {:?}",
+                            write.resource_type.as_str(),
+                            write.resource_id,
+                            write.base_version,
+                            conflict.server_version
+                        );
+                        self.outbox.record_conflict(&conflict).await?;
+                        self.events.publish(DomainEvent::ConflictDetected {
+                            conflict_id: conflict.id,
+                            resource_type: write.resource_type.fhir_type().to_string(),
+                            resource_id: write.resource_id,
+                        });
+                        report.conflicts += 1;
+                    }
+                    Err(e) if is_unreachable(&e) => {
+                        tracing::info!("Sync server unreachable, keeping queued writes: {}", e);
+                        report.offline = true;
+                        break 'batches;
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to sync {}/{}: {:#}",
+                            write.resource_type.as_str(),
+                            write.resource_id,
+                            e
+                        );
+                        self.outbox.record_failure(write.id, &format!("{:#}", e)).await?;
+                        failed.push(write.id);
+                        report.failed += 1;
+                    }
+                }
+                self.status.send_modify(|status| {
+                    if let Some(progress) = &mut status.progress {
+                        progress.completed += 1;
+                    }
+                });
+            }
+        }
+
+        report.remaining = self.outbox.pending_count().await?;
+        Ok(report)
+    }
+
+    /// Sync every `interval` while writes are queued. Passes while offline
+    /// simply leave the queue for the next one.
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.refresh_status().await {
+                    tracing::error!("Failed to read sync status: {:#}", e);
+                }
+                match self.outbox.pending_count().await {
+                    Ok(0) => continue,
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::error!("Failed to read sync queue: {:#}", e);
+                        continue;
+                    }
+                }
+                match self.sync_once().await {
+                    Ok(report) if !report.offline => tracing::info!(
+                        "Sync pass: {} applied, {} conflicts, {} failed, {} remaining",
+                        report.applied,
+                        report.conflicts,
+                        report.failed,
+                        report.remaining
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Sync pass failed: {:#}", e),
+                }
+            }
+        })
+    }
+
+    /// Apply one write in a server transaction, or report why it can't be
+    pub async fn replay(&self, write: &PendingWrite) -> Result<ReplayOutcome> {
+        let table = write.resource_type.table();
+        let mut tx = self.server.begin().await.context("Failed to reach sync server")?;
+
+        let current: Option<Value> = sqlx::query_scalar(&sql::lock_row(table))
+            .bind(write.resource_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let meta = current.as_ref().and_then(|row| row.get("meta"));
+        let server_version = meta
+            .and_then(|meta| meta.get("version_id"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let server_vector: VersionVector = meta
+            .and_then(|meta| meta.get("version_vector"))
+            .and_then(|vector| serde_json::from_value(vector.clone()).ok())
+            .unwrap_or_default();
+
+        if current.is_some() && server_vector.includes(&write.version_vector) {
+            return Ok(ReplayOutcome::AlreadyApplied);
+        }
+
+        let applies = match (write.operation, &current) {
+            (SyncOperation::Create, None) => true,
+            (SyncOperation::Create, Some(_)) | (_, None) => false,
+            (_, Some(_)) => server_version == write.base_version,
+        };
+        if !applies {
+            return Ok(ReplayOutcome::Conflict(Box::new(SyncConflict {
+                id: Uuid::new_v4(),
+                local_write: write.clone(),
+                server_version,
+                server_vector,
+                server_state: current,
+                detected_at: Utc::now(),
+            })));
+        }
+
+        // The engine's pool is not the API's, so attribute the versions the
+        // history trigger records here, for this transaction only
+        sqlx::query(sql::SET_CHANGE_CONTEXT)
+            .bind(write.user_id.to_string())
+            .bind(format!("sync:{}", self.outbox.node_id()))
+            .execute(&mut *tx)
+            .await?;
+
+        let columns: Vec<&str> = write
+            .resource_type
+            .columns()
+            .iter()
+            .copied()
+            .filter(|column| write.payload.get(column).is_some())
+            .collect();
+        match write.operation {
+            SyncOperation::Create => {
+                // `enqueue` only takes payloads with an `id`, so it is among the columns
+                sqlx::query(&sql::insert_row(table, &columns))
+                    .bind(&write.payload)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            SyncOperation::Update => {
+                let columns: Vec<&str> = columns.into_iter().filter(|column| *column != "id").collect();
+                if !columns.is_empty() {
+                    sqlx::query(&sql::update_row(table, &columns))
+                        .bind(&write.payload)
+                        .bind(write.resource_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+            SyncOperation::Delete => {
+                sqlx::query(write.resource_type.soft_delete_sql())
+                    .bind(write.resource_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        let version = next_version_id(server_version.as_deref());
+        let mut version_vector = server_vector;
+        version_vector.merge(&write.version_vector);
+        Self::stamp(&mut tx, table, write.resource_id, &version, &version_vector).await?;
+        AuditService::create_audit_log_in(&mut tx, &Self::audit(write, self.outbox.node_id())).await?;
+
+        tx.commit().await?;
+        Ok(ReplayOutcome::Applied { version })
+    }
+
+    /// Audit entry for a replayed write, recorded against the user who made
+    /// it offline
+    fn audit(write: &PendingWrite, node_id: &str) -> AuditLog {
+        let (event_type, action) = match write.operation {
+            SyncOperation::Create => (AuditEventType::Create, AuditAction::Create),
+            SyncOperation::Update => (AuditEventType::Update, AuditAction::Update),
+            SyncOperation::Delete => (AuditEventType::Delete, AuditAction::Delete),
+        };
+        let audit = AuditLog::new(event_type, action, write.resource_type.as_str().to_string())
+            .with_user(write.user_id)
+            .with_resource(write.resource_id)
+            .with_details(format!("Offline write {} synced from {}", write.id, node_id));
+        let patient_id = match write.resource_type {
+            SyncResourceType::Patient => Some(write.resource_id),
+            _ => write
+                .payload
+                .get("patient_id")
+                .and_then(Value::as_str)
+                .and_then(|id| Uuid::parse_str(id).ok()),
+        };
+        match patient_id {
+            Some(patient_id) => audit.with_patient(patient_id),
+            None => audit,
+        }
+    }
+
+    async fn stamp(
+        tx: &mut Transaction<'_, Postgres>,
+        table: &str,
+        resource_id: Uuid,
+        version: &str,
+        version_vector: &VersionVector,
+    ) -> Result<()> {
+        sqlx::query(&sql::stamp_version(table))
+            .bind(resource_id)
+            .bind(version)
+            .bind(Utc::now().to_rfc3339())
+            .bind(serde_json::to_value(version_vector)?)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Whether the error means the server could not be reached, as opposed to
+/// the server rejecting the write
+fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed)
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_errors_are_unreachable() {
+        let offline = anyhow::Error::new(sqlx::Error::PoolTimedOut).context("Failed to reach sync server");
+        let rejected = anyhow::Error::new(sqlx::Error::RowNotFound);
+        assert!(is_unreachable(&offline));
+        assert!(!is_unreachable(&rejected));
+    }
+}
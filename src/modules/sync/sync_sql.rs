@@ -0,0 +1,316 @@
+// src/modules/sync/sync_sql.rs
+//! SQL for the offline sync subsystem
+
+/// Local outbox kept in the desktop app's SQLite database.
+///
+/// Identifiers are stored as TEXT, JSON as TEXT and timestamps as RFC 3339
+/// strings, matching the SQLite authorization storage.
+pub mod sqlite {
+    /// Create the outbox schema if it does not exist
+    pub const CREATE_SCHEMA: &str = r#"
+        CREATE TABLE IF NOT EXISTS sync_state (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS sync_outbox (
+            sequence INTEGER PRIMARY KEY AUTOINCREMENT,
+            id TEXT NOT NULL UNIQUE,
+            resource_type TEXT NOT NULL,
+            resource_id TEXT NOT NULL,
+            operation TEXT NOT NULL CHECK (operation IN ('create', 'update', 'delete')),
+            user_id TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            base_version TEXT,
+            version_vector TEXT NOT NULL DEFAULT '{}',
+            queued_at TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            UNIQUE (resource_type, resource_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS sync_conflicts (
+            id TEXT PRIMARY KEY,
+            resource_type TEXT NOT NULL,
+            resource_id TEXT NOT NULL,
+            local_write TEXT NOT NULL,
+            server_version TEXT,
+            server_vector TEXT NOT NULL DEFAULT '{}',
+            server_state TEXT,
+            detected_at TEXT NOT NULL,
+            UNIQUE (resource_type, resource_id)
+        );
+    "#;
+
+    pub const GET_STATE: &str = r#"
+        SELECT value FROM sync_state WHERE key = ?1
+    "#;
+
+    pub const SET_STATE: &str = r#"
+        INSERT INTO sync_state (key, value) VALUES (?1, ?2)
+        ON CONFLICT (key) DO NOTHING
+    "#;
+
+    pub const PUT_STATE: &str = r#"
+        INSERT INTO sync_state (key, value) VALUES (?1, ?2)
+        ON CONFLICT (key) DO UPDATE SET value = excluded.value
+    "#;
+
+    const OUTBOX_COLUMNS: &str = "id, resource_type, resource_id, operation, user_id, payload, base_version, version_vector, queued_at, attempts, last_error";
+
+    pub const INSERT_PENDING: &str = r#"
+        INSERT INTO sync_outbox (
+            id, resource_type, resource_id, operation, user_id, payload,
+            base_version, version_vector, queued_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+    "#;
+
+    /// Replace a queued write in place, keeping its position in the queue
+    pub const UPDATE_PENDING: &str = r#"
+        UPDATE sync_outbox
+        SET operation = ?2, user_id = ?3, payload = ?4, version_vector = ?5, queued_at = ?6,
+            attempts = 0, last_error = NULL
+        WHERE id = ?1
+    "#;
+
+    pub const DELETE_PENDING: &str = r#"
+        DELETE FROM sync_outbox WHERE id = ?1
+    "#;
+
+    pub const RECORD_FAILURE: &str = r#"
+        UPDATE sync_outbox SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1
+    "#;
+
+    pub const COUNT_PENDING: &str = r#"
+        SELECT COUNT(*) FROM sync_outbox
+    "#;
+
+    pub fn get_pending_for_resource() -> String {
+        format!(
+            "SELECT {} FROM sync_outbox WHERE resource_type = ?1 AND resource_id = ?2",
+            OUTBOX_COLUMNS
+        )
+    }
+
+    pub fn list_pending() -> String {
+        format!("SELECT {} FROM sync_outbox ORDER BY sequence LIMIT ?1", OUTBOX_COLUMNS)
+    }
+
+    pub const INSERT_CONFLICT: &str = r#"
+        INSERT INTO sync_conflicts (
+            id, resource_type, resource_id, local_write, server_version,
+            server_vector, server_state, detected_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+    "#;
+
+    pub const HAS_CONFLICT: &str = r#"
+        SELECT EXISTS (
+            SELECT 1 FROM sync_conflicts WHERE resource_type = ?1 AND resource_id = ?2
+        )
+    "#;
+
+    pub const COUNT_CONFLICTS: &str = r#"
+        SELECT COUNT(*) FROM sync_conflicts
+    "#;
+
+    pub const LIST_CONFLICTS: &str = r#"
+        SELECT id, local_write, server_version, server_vector, server_state, detected_at
+        FROM sync_conflicts
+        ORDER BY detected_at
+    "#;
+
+    pub const GET_CONFLICT: &str = r#"
+        SELECT id, local_write, server_version, server_vector, server_state, detected_at
+        FROM sync_conflicts
+        WHERE id = ?1
+    "#;
+
+    pub const DELETE_CONFLICT: &str = r#"
+        DELETE FROM sync_conflicts WHERE id = ?1
+    "#;
+
+    /// Create the local copies of resources and their audit log. Resources
+    /// are kept whole as JSON, with the columns they are looked up by.
+    pub const CREATE_LOCAL_SCHEMA: &str = r#"
+        CREATE TABLE IF NOT EXISTS local_patients (
+            id TEXT PRIMARY KEY,
+            search_text TEXT NOT NULL,
+            active INTEGER NOT NULL,
+            resource TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS local_appointments (
+            id TEXT PRIMARY KEY,
+            patient_id TEXT,
+            status TEXT NOT NULL,
+            start_time TEXT,
+            end_time TEXT,
+            resource TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS local_appointments_start ON local_appointments (start_time);
+        CREATE INDEX IF NOT EXISTS local_appointments_patient ON local_appointments (patient_id);
+
+        CREATE TABLE IF NOT EXISTS local_medical_records (
+            id TEXT PRIMARY KEY,
+            patient_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            resource TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS local_medical_records_patient ON local_medical_records (patient_id);
+
+        CREATE TABLE IF NOT EXISTS local_audit_log (
+            id TEXT PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            entry TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS local_audit_log_timestamp ON local_audit_log (timestamp);
+    "#;
+
+    pub const UPSERT_LOCAL_PATIENT: &str = r#"
+        INSERT INTO local_patients (id, search_text, active, resource, updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT (id) DO UPDATE SET
+            search_text = excluded.search_text, active = excluded.active,
+            resource = excluded.resource, updated_at = excluded.updated_at
+    "#;
+
+    pub const GET_LOCAL_PATIENT: &str = r#"
+        SELECT resource FROM local_patients WHERE id = ?1
+    "#;
+
+    pub const DELETE_LOCAL_PATIENT: &str = r#"
+        DELETE FROM local_patients WHERE id = ?1
+    "#;
+
+    /// Active patients whose names or identifiers contain `?1`, lowercased
+    pub const SEARCH_LOCAL_PATIENTS: &str = r#"
+        SELECT resource FROM local_patients
+        WHERE active = 1 AND search_text LIKE '%' || ?1 || '%'
+        ORDER BY updated_at DESC
+        LIMIT ?2
+    "#;
+
+    pub const UPSERT_LOCAL_APPOINTMENT: &str = r#"
+        INSERT INTO local_appointments (id, patient_id, status, start_time, end_time, resource)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ON CONFLICT (id) DO UPDATE SET
+            patient_id = excluded.patient_id, status = excluded.status, start_time = excluded.start_time,
+            end_time = excluded.end_time, resource = excluded.resource
+    "#;
+
+    pub const GET_LOCAL_APPOINTMENT: &str = r#"
+        SELECT resource FROM local_appointments WHERE id = ?1
+    "#;
+
+    pub const DELETE_LOCAL_APPOINTMENT: &str = r#"
+        DELETE FROM local_appointments WHERE id = ?1
+    "#;
+
+    /// Appointments starting in `[?2, ?3)`, of patient `?1` when not NULL
+    pub const LIST_LOCAL_APPOINTMENTS: &str = r#"
+        SELECT resource FROM local_appointments
+        WHERE (?1 IS NULL OR patient_id = ?1) AND start_time >= ?2 AND start_time < ?3
+        ORDER BY start_time
+    "#;
+
+    /// Appointments holding a slot that overlaps `[?1, ?2)`
+    pub const OVERLAPPING_LOCAL_APPOINTMENTS: &str = r#"
+        SELECT resource FROM local_appointments
+        WHERE status IN ('proposed', 'pending', 'booked', 'arrived', 'checked-in')
+          AND start_time < ?2 AND end_time > ?1
+    "#;
+
+    pub const UPSERT_LOCAL_MEDICAL_RECORD: &str = r#"
+        INSERT INTO local_medical_records (id, patient_id, status, created_at, resource)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT (id) DO UPDATE SET status = excluded.status, resource = excluded.resource
+    "#;
+
+    pub const GET_LOCAL_MEDICAL_RECORD: &str = r#"
+        SELECT resource FROM local_medical_records WHERE id = ?1
+    "#;
+
+    pub const DELETE_LOCAL_MEDICAL_RECORD: &str = r#"
+        DELETE FROM local_medical_records WHERE id = ?1
+    "#;
+
+    pub const LIST_LOCAL_MEDICAL_RECORDS: &str = r#"
+        SELECT resource FROM local_medical_records WHERE patient_id = ?1 ORDER BY created_at DESC
+    "#;
+
+    pub const INSERT_LOCAL_AUDIT: &str = r#"
+        INSERT INTO local_audit_log (id, timestamp, entry) VALUES (?1, ?2, ?3)
+    "#;
+
+    /// Audit entries in `[?1, ?2)`, oldest first
+    pub const LIST_LOCAL_AUDIT: &str = r#"
+        SELECT entry FROM local_audit_log WHERE timestamp >= ?1 AND timestamp < ?2 ORDER BY timestamp
+    "#;
+}
+
+/// Replay against the central PostgreSQL server. Table and column names
+/// come from `SyncResourceType`, never from the queued payload.
+pub mod postgres {
+    /// Current row as JSON, locked until the replay commits
+    pub fn lock_row(table: &str) -> String {
+        format!("SELECT to_jsonb(t) FROM {} t WHERE t.id = $1 FOR UPDATE", table)
+    }
+
+    /// Insert the payload's columns, converted with the table's row type
+    pub fn insert_row(table: &str, columns: &[&str]) -> String {
+        let columns = columns.join(", ");
+        format!(
+            "INSERT INTO {table} ({columns}) SELECT {columns} FROM jsonb_populate_record(NULL::{table}, $1)",
+            table = table,
+            columns = columns
+        )
+    }
+
+    /// Overwrite the payload's columns on the row with ID `$2`
+    pub fn update_row(table: &str, columns: &[&str]) -> String {
+        let assignments = columns
+            .iter()
+            .map(|column| format!("{column} = r.{column}", column = column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "UPDATE {table} t SET {assignments} FROM jsonb_populate_record(NULL::{table}, $1) r WHERE t.id = $2",
+            table = table,
+            assignments = assignments
+        )
+    }
+
+    /// Set the user (`$1`) and source system (`$2`) the `record_resource_history`
+    /// trigger records with the versions of this transaction
+    pub const SET_CHANGE_CONTEXT: &str = r#"
+        SELECT set_config('app.user_id', $1, true), set_config('app.change_reason', '', true),
+               set_config('app.change_source', $2, true)
+    "#;
+
+    /// Record the new version and merged version vector in `meta`
+    pub fn stamp_version(table: &str) -> String {
+        format!(
+            r#"UPDATE {} SET meta = jsonb_set(jsonb_set(jsonb_set(
+                   COALESCE(meta, '{{}}'::jsonb),
+                   '{{version_id}}', to_jsonb($2::text)),
+                   '{{last_updated}}', to_jsonb($3::text)),
+                   '{{version_vector}}', $4)
+               WHERE id = $1"#,
+            table
+        )
+    }
+
+    pub const SOFT_DELETE_PATIENT: &str = r#"
+        UPDATE patients SET active = false WHERE id = $1
+    "#;
+
+    pub const SOFT_DELETE_APPOINTMENT: &str = r#"
+        UPDATE appointments SET status = 'entered-in-error' WHERE id = $1
+    "#;
+
+    pub const SOFT_DELETE_MEDICAL_RECORD: &str = r#"
+        UPDATE medical_records SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL
+    "#;
+}
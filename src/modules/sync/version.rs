@@ -0,0 +1,108 @@
+// src/modules/sync/version.rs
+//! Version vectors for offline writes
+//!
+//! Each node (a desktop install, or the central server) counts the writes it
+//! has made to a resource. Comparing two vectors tells whether one history
+//! includes the other or whether they diverged.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// How two version vectors relate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Causality {
+    Equal,
+    /// The left vector is an ancestor of the right one
+    Before,
+    /// The left vector includes the right one
+    After,
+    /// Both sides have writes the other has not seen
+    Concurrent,
+}
+
+/// Per-node write counters for one resource
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VersionVector(BTreeMap<String, u64>);
+
+impl VersionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes made by `node`
+    pub fn get(&self, node: &str) -> u64 {
+        self.0.get(node).copied().unwrap_or(0)
+    }
+
+    /// Record one more write by `node`
+    pub fn increment(&mut self, node: &str) {
+        *self.0.entry(node.to_string()).or_insert(0) += 1;
+    }
+
+    /// Pointwise maximum, the history containing both vectors
+    pub fn merge(&mut self, other: &VersionVector) {
+        for (node, count) in &other.0 {
+            let entry = self.0.entry(node.clone()).or_insert(0);
+            *entry = (*entry).max(*count);
+        }
+    }
+
+    pub fn compare(&self, other: &VersionVector) -> Causality {
+        let mut less = false;
+        let mut greater = false;
+        for node in self.0.keys().chain(other.0.keys()) {
+            match self.get(node).cmp(&other.get(node)) {
+                Ordering::Less => less = true,
+                Ordering::Greater => greater = true,
+                Ordering::Equal => {}
+            }
+        }
+        match (less, greater) {
+            (false, false) => Causality::Equal,
+            (true, false) => Causality::Before,
+            (false, true) => Causality::After,
+            (true, true) => Causality::Concurrent,
+        }
+    }
+
+    /// Whether this history already includes every write in `other`
+    pub fn includes(&self, other: &VersionVector) -> bool {
+        matches!(self.compare(other), Causality::Equal | Causality::After)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(entries: &[(&str, u64)]) -> VersionVector {
+        VersionVector(entries.iter().map(|(node, count)| (node.to_string(), *count)).collect())
+    }
+
+    #[test]
+    fn test_compare() {
+        let base = vector(&[("server", 2)]);
+        let local = vector(&[("server", 2), ("desk-1", 1)]);
+        let remote = vector(&[("server", 3)]);
+
+        assert_eq!(base.compare(&base), Causality::Equal);
+        assert_eq!(base.compare(&local), Causality::Before);
+        assert_eq!(local.compare(&base), Causality::After);
+        assert_eq!(local.compare(&remote), Causality::Concurrent);
+        assert!(local.includes(&base));
+        assert!(!remote.includes(&local));
+    }
+
+    #[test]
+    fn test_merge_takes_pointwise_max() {
+        let mut local = vector(&[("server", 2), ("desk-1", 1)]);
+        local.merge(&vector(&[("server", 3)]));
+        assert_eq!(local, vector(&[("server", 3), ("desk-1", 1)]));
+
+        local.increment("desk-1");
+        assert_eq!(local.get("desk-1"), 2);
+        assert_eq!(local.get("desk-2"), 0);
+    }
+}
@@ -0,0 +1,54 @@
+//! Task Module
+//!
+//! This module provides work items as FHIR R4 Tasks:
+//! - Tasks scheduled from care plan activities, or created on their own
+//! - Assignment to members of the patient's or care plan's care team
+//! - Status changes along the FHIR task state machine
+//! - Overdue work lists per owner or patient
+//! - Versioned updates guarded by `If-Match`
+//! - Access checked against the patient, and every access audited
+
+#[path = "task.controller.rs"]
+pub mod task_controller;
+#[path = "task.service.rs"]
+pub mod task_service;
+#[path = "task.sql.rs"]
+pub mod task_sql;
+
+pub use task_controller::TaskController;
+pub use task_service::{TaskRequest, TaskSearch, TaskSearchResult, TaskService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::audit::AuditService;
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::events::EventBus;
+use crate::utils::api_router::ApiRouter;
+
+/// Task Module Configuration
+pub struct TaskModule {
+    pub service: Arc<TaskService>,
+    pub controller: Arc<TaskController>,
+}
+
+impl TaskModule {
+    /// Create a new Task Module with dependency injection
+    pub fn new(db_pool: PgPool, events: EventBus, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        let audit_service = Arc::new(AuditService::new(db_pool.clone()));
+        let service = Arc::new(TaskService::with_events(db_pool, events));
+        let controller = Arc::new(TaskController::new(service.clone(), authorization_engine, audit_service));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<TaskService> {
+        self.service.clone()
+    }
+}
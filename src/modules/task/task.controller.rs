@@ -0,0 +1,516 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::TASK_RESOURCE_TYPE;
+use crate::models::{AuditAction, AuditEventType, AuditLog, CodeableConcept, Reference, ResourceMeta, Task};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::task::task_service::{TaskRequest, TaskSearch, TaskSearchResult};
+use crate::modules::task::TaskService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::etag::{if_match_version, precondition_status, versioned, Versioned};
+use crate::utils::fhir_search::{EntrySearch, SearchEntryMode};
+use crate::utils::pagination::{page_links, BundleLink};
+
+/// FHIR R4 Task as served
+#[derive(Debug, Serialize)]
+pub struct TaskResponse {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    /// The care plan the task was scheduled from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(rename = "basedOn")]
+    pub based_on: Vec<Reference>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "statusReason")]
+    pub status_reason: Option<CodeableConcept>,
+    pub intent: String,
+    pub priority: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "for")]
+    pub for_: Reference,
+    #[serde(rename = "authoredOn")]
+    pub authored_on: DateTime<Utc>,
+    #[serde(rename = "lastModified")]
+    pub last_modified: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Reference>,
+    /// When the task is due, as the end of its restriction period
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restriction: Option<TaskRestriction>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+/// FHIR Task.restriction
+#[derive(Debug, Serialize)]
+pub struct TaskRestriction {
+    pub period: Period,
+}
+
+/// FHIR Period
+#[derive(Debug, Serialize)]
+pub struct Period {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// FHIR Annotation, as Task.note carries it
+#[derive(Debug, Serialize)]
+pub struct Annotation {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskBundle {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub link: Vec<BundleLink>,
+    pub entry: Vec<TaskBundleEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskBundleEntry {
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    pub resource: TaskResponse,
+    /// Set in searchsets only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<EntrySearch>,
+}
+
+/// Whose overdue tasks to list; everyone's when neither is given
+#[derive(Debug, Default, Deserialize)]
+pub struct OverdueQuery {
+    pub owner: Option<Uuid>,
+    pub patient: Option<Uuid>,
+}
+
+/// Who to give a task to
+#[derive(Debug, Deserialize)]
+pub struct AssignRequest {
+    pub owner_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Task controller for FHIR R4 Tasks and overdue work lists. A task is
+/// work on its patient's care: reading it takes read access to the patient
+/// and creating, changing, assigning or removing it update access, and each
+/// is audited against the patient.
+pub struct TaskController {
+    state: TaskState,
+}
+
+#[derive(Clone)]
+pub struct TaskState {
+    service: Arc<TaskService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    audit_service: Arc<AuditService>,
+}
+
+impl TaskController {
+    /// Create new controller with injected services
+    pub fn new(
+        task_service: Arc<TaskService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        audit_service: Arc<AuditService>,
+    ) -> Self {
+        Self {
+            state: TaskState {
+                service: task_service,
+                authorization_engine,
+                audit_service,
+            },
+        }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/overdue", Self::overdue_tasks, "List open tasks past their due date")
+            .post("/", Self::create_task, "Create a task")
+            .get("/", Self::search_tasks, "Search tasks with query parameters")
+            .get("/:id", Self::get_task, "Get task by ID")
+            .put("/:id", Self::update_task, "Update task")
+            .delete("/:id", Self::delete_task, "Delete task")
+            .post("/:id/assign", Self::assign_task, "Assign a task to a care-team member")
+            .with_state(self.state.clone())
+    }
+
+    /// Create a task
+    pub async fn create_task(
+        State(state): State<TaskState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Json(request): Json<TaskRequest>,
+    ) -> Result<(StatusCode, Versioned<TaskResponse>), ErrorReply> {
+        tracing::info!("Creating task for patient {}", request.patient_id);
+
+        let context = "Failed to create task";
+        Self::authorize(&state, &auth, &headers, Action::Update, request.patient_id, context).await?;
+        match state.service.create(request).await {
+            Ok(task) => {
+                tracing::info!("Task created successfully: {}", task.id);
+                Self::audit(&state, &auth, (AuditEventType::Create, AuditAction::Create), &task, context).await?;
+                Ok((StatusCode::CREATED, versioned(&task.meta.clone(), Self::task_to_response(task))))
+            }
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Get task by ID
+    pub async fn get_task(
+        State(state): State<TaskState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Versioned<TaskResponse>, ErrorReply> {
+        let context = "Failed to get task";
+        let task = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Read, task.patient_id, context).await?;
+        Self::audit(&state, &auth, (AuditEventType::Access, AuditAction::Read), &task, context).await?;
+        Ok(versioned(&task.meta.clone(), Self::task_to_response(task)))
+    }
+
+    /// Search tasks with query parameters. Tasks of patients the user may
+    /// not read are left out of the page.
+    pub async fn search_tasks(
+        State(state): State<TaskState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<Json<TaskBundle>, ErrorReply> {
+        tracing::info!("Searching tasks with params: {:?}", params);
+
+        let context = "Failed to search tasks";
+        let search =
+            TaskSearch::from_params(&params).map_err(|e| Self::error_response("Invalid search parameters", e))?;
+        let mut result = state.service.search(&search).await.map_err(|e| Self::error_response(context, e))?;
+        result.tasks = Self::readable(&state, &auth, &headers, result.tasks, context).await?;
+        tracing::info!("Found {} tasks", result.tasks.len());
+
+        let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+        Ok(Json(Self::tasks_to_bundle(result, link)))
+    }
+
+    /// Open tasks past their due date as a collection Bundle, the longest
+    /// overdue first. Tasks of patients the user may not read are left out.
+    pub async fn overdue_tasks(
+        State(state): State<TaskState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Query(query): Query<OverdueQuery>,
+    ) -> Result<Json<TaskBundle>, ErrorReply> {
+        let context = "Failed to list overdue tasks";
+        let tasks = state
+            .service
+            .overdue(Utc::now(), query.owner, query.patient)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        let tasks = Self::readable(&state, &auth, &headers, tasks, context).await?;
+        Ok(Json(Self::bundle("collection", tasks, None, Vec::new(), None)))
+    }
+
+    /// Update task; `If-Match` must name the version being replaced
+    pub async fn update_task(
+        State(state): State<TaskState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<TaskRequest>,
+    ) -> Result<Versioned<TaskResponse>, ErrorReply> {
+        tracing::info!("Updating task: {}", id);
+
+        let expected_version = if_match_version(&headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the task's ETag in If-Match to update it".to_string(),
+                }),
+            )
+        })?;
+
+        let context = "Failed to update task";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.update(id, request, &expected_version).await {
+            Ok(Some(task)) => {
+                Self::audit(&state, &auth, (AuditEventType::Update, AuditAction::Update), &task, context).await?;
+                Ok(versioned(&task.meta.clone(), Self::task_to_response(task)))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Give a task to a member of the patient's or care plan's care team
+    pub async fn assign_task(
+        State(state): State<TaskState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<AssignRequest>,
+    ) -> Result<Versioned<TaskResponse>, ErrorReply> {
+        tracing::info!("Assigning task {} to {}", id, request.owner_id);
+
+        let context = "Failed to assign task";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.assign(id, request.owner_id).await {
+            Ok(Some(task)) => {
+                Self::audit(&state, &auth, (AuditEventType::Update, AuditAction::Update), &task, context).await?;
+                Ok(versioned(&task.meta.clone(), Self::task_to_response(task)))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Delete task
+    pub async fn delete_task(
+        State(state): State<TaskState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Deleting task: {}", id);
+
+        let context = "Failed to delete task";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.delete(id).await {
+            Ok(true) => {
+                Self::audit(&state, &auth, (AuditEventType::Delete, AuditAction::Delete), &current, context).await?;
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// The task, or 404
+    async fn existing(state: &TaskState, id: Uuid, context: &str) -> Result<Task, ErrorReply> {
+        match state.service.get(id).await {
+            Ok(Some(task)) => Ok(task),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Fail with 403 unless the user may take `action` on the patient
+    async fn authorize(
+        state: &TaskState,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        action: Action,
+        patient_id: Uuid,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), auth, headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        authorizer
+            .authorize(action, Resource::Patient(patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// The tasks whose patients the user may read, each access audited
+    async fn readable(
+        state: &TaskState,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        tasks: Vec<Task>,
+        context: &str,
+    ) -> Result<Vec<Task>, ErrorReply> {
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), auth, headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        let found = tasks.len();
+        let tasks = authorizer
+            .retain(Action::Read, tasks, |task| Resource::Patient(task.patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        if tasks.len() < found {
+            tracing::info!("Withheld {} of {} tasks from user {}", found - tasks.len(), found, auth.user_id);
+        }
+        for task in &tasks {
+            Self::audit(state, auth, (AuditEventType::Access, AuditAction::Read), task, context).await?;
+        }
+        Ok(tasks)
+    }
+
+    /// Record the user's access to or change of a task
+    async fn audit(
+        state: &TaskState,
+        auth: &AuthContext,
+        (event_type, action): (AuditEventType, AuditAction),
+        task: &Task,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let audit_log = auth
+            .audit(AuditLog::new(event_type, action, TASK_RESOURCE_TYPE.to_string()))
+            .with_patient(task.patient_id)
+            .with_resource(task.id)
+            .with_details(format!("Task: {}", task.status.as_str()));
+        state
+            .audit_service
+            .create_audit_log(&audit_log)
+            .await
+            .map(|_| ())
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// Convert Task model to FHIR response format
+    pub fn task_to_response(task: Task) -> TaskResponse {
+        let practitioner = |id: Uuid| Reference {
+            reference: format!("Practitioner/{}", id),
+            display: None,
+        };
+
+        TaskResponse {
+            resource_type: TASK_RESOURCE_TYPE.to_string(),
+            id: task.id,
+            meta: task.meta,
+            based_on: task
+                .care_plan_id
+                .map(|id| Reference {
+                    reference: format!("CarePlan/{}", id),
+                    display: None,
+                })
+                .into_iter()
+                .collect(),
+            status: task.status.as_str().to_string(),
+            status_reason: task.status_reason.map(|text| CodeableConcept {
+                coding: Vec::new(),
+                text: Some(text),
+            }),
+            intent: "order".to_string(),
+            priority: task.priority.as_str().to_string(),
+            code: task.code,
+            description: task.description,
+            for_: Reference {
+                reference: format!("Patient/{}", task.patient_id),
+                display: None,
+            },
+            authored_on: task.authored_on,
+            last_modified: task.last_modified,
+            requester: task.requester_id.map(practitioner),
+            owner: task.owner_id.map(practitioner),
+            restriction: task.due.map(|due| TaskRestriction {
+                period: Period {
+                    start: None,
+                    end: Some(due),
+                },
+            }),
+            note: task.note.into_iter().map(|text| Annotation { text }).collect(),
+        }
+    }
+
+    /// Convert a page of search results to a FHIR searchset Bundle
+    fn tasks_to_bundle(result: TaskSearchResult, link: Vec<BundleLink>) -> TaskBundle {
+        let search = EntrySearch {
+            mode: SearchEntryMode::Match,
+        };
+        Self::bundle("searchset", result.tasks, result.total, link, Some(search))
+    }
+
+    fn bundle(
+        bundle_type: &str,
+        tasks: Vec<Task>,
+        total: Option<i64>,
+        link: Vec<BundleLink>,
+        search: Option<EntrySearch>,
+    ) -> TaskBundle {
+        let entry = tasks
+            .into_iter()
+            .map(|task| TaskBundleEntry {
+                full_url: format!("Task/{}", task.id),
+                resource: Self::task_to_response(task),
+                search: search.clone(),
+            })
+            .collect();
+
+        TaskBundle {
+            resource_type: "Bundle".to_string(),
+            id: Uuid::new_v4(),
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: Utc::now(),
+                profile: vec!["http://hl7.org/fhir/StructureDefinition/Bundle".to_string()],
+                security: vec![],
+                tag: vec![],
+            },
+            bundle_type: bundle_type.to_string(),
+            total,
+            link,
+            entry,
+        }
+    }
+
+    fn not_found(id: Uuid) -> ErrorReply {
+        tracing::warn!("Task not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Task not found".to_string(),
+                message: format!("Task with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => precondition_status(&e).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,655 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{CodeableConcept, Task, TaskPriority, TaskStatus};
+use crate::modules::authorization::PostgresAuthorizationStorage;
+use crate::modules::care_plan::CareTeam;
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{reference_id, split_modifier, SearchParamDefinition, TotalMode};
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
+
+// Import SQL queries from separate file
+use crate::modules::task::task_sql::*;
+
+/// Task from a row of any query selecting its columns
+fn task_from_row(row: &PgRow) -> Task {
+    Task {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        care_plan_id: row.get("care_plan_id"),
+        activity_id: row.get("activity_id"),
+        status: TaskStatus::from_string(row.get("status")),
+        status_reason: row.get("status_reason"),
+        priority: TaskPriority::from_string(row.get("priority")),
+        code: row
+            .get::<Option<serde_json::Value>, _>("code")
+            .and_then(|code| serde_json::from_value(code).ok()),
+        description: row.get("description"),
+        owner_id: row.get("owner_id"),
+        requester_id: row.get("requester_id"),
+        due: row.get("due"),
+        authored_on: row.get("authored_on"),
+        last_modified: row.get("last_modified"),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
+    }
+}
+
+/// Check a task before it is saved: a task for a care plan activity names
+/// the plan too, and a task says what is to be done
+pub fn check_task(task: &Task) -> Result<(), HimsError> {
+    let invalid = |message: &str| HimsError::ValidationError { message: message.to_string() };
+
+    if task.activity_id.is_some() && task.care_plan_id.is_none() {
+        return Err(invalid("A task for a care plan activity must name the care plan"));
+    }
+    if task.code.is_none() && task.description.as_deref().unwrap_or("").trim().is_empty() {
+        return Err(invalid("A task needs a code or a description of what is to be done"));
+    }
+    Ok(())
+}
+
+/// A task as clients send it to create or replace one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRequest {
+    pub patient_id: Uuid,
+    pub care_plan_id: Option<Uuid>,
+    pub activity_id: Option<Uuid>,
+    #[serde(default)]
+    pub status: TaskStatus,
+    pub status_reason: Option<String>,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    pub code: Option<CodeableConcept>,
+    pub description: Option<String>,
+    pub owner_id: Option<Uuid>,
+    pub requester_id: Option<Uuid>,
+    pub due: Option<DateTime<Utc>>,
+    pub note: Option<String>,
+}
+
+impl From<TaskRequest> for Task {
+    fn from(request: TaskRequest) -> Self {
+        let mut task = Task::new(request.patient_id);
+        task.care_plan_id = request.care_plan_id;
+        task.activity_id = request.activity_id;
+        task.status = request.status;
+        task.status_reason = request.status_reason;
+        task.priority = request.priority;
+        task.code = request.code;
+        task.description = request.description;
+        task.owner_id = request.owner_id;
+        task.requester_id = request.requester_id;
+        task.due = request.due;
+        task.note = request.note;
+        task
+    }
+}
+
+/// Page size of a task search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 50;
+
+/// Largest page a task search returns
+pub const MAX_SEARCH_COUNT: i64 = 200;
+
+/// Paging of task searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Order of task searches, soonest due first, as SEARCH_TASKS keys its rows
+const SEARCH_KEYS: [SortKey; 1] = [SortKey::new("COALESCE(due, 'infinity')", "timestamptz", false)];
+
+/// Most overdue tasks listed at once
+pub const MAX_OVERDUE_TASKS: i64 = 500;
+
+/// Parsed task search
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskSearch {
+    pub patient: Vec<Uuid>,
+    pub owner: Vec<Uuid>,
+    /// Care plans the tasks were scheduled from
+    pub based_on: Vec<Uuid>,
+    /// Status codes; any matches
+    pub status: Vec<String>,
+    /// Priority codes; any matches
+    pub priority: Vec<String>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for TaskSearch {
+    fn default() -> Self {
+        Self {
+            patient: Vec::new(),
+            owner: Vec::new(),
+            based_on: Vec::new(),
+            status: Vec::new(),
+            priority: Vec::new(),
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// A page of task search results
+#[derive(Debug)]
+pub struct TaskSearchResult {
+    pub tasks: Vec<Task>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl TaskSearch {
+    /// Search parameters `from_params` supports, for the CapabilityStatement
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "patient",
+            param_type: "reference",
+            documentation: "The patient the task is for",
+        },
+        SearchParamDefinition {
+            name: "owner",
+            param_type: "reference",
+            documentation: "The care-team member responsible for the task",
+        },
+        SearchParamDefinition {
+            name: "based-on",
+            param_type: "reference",
+            documentation: "The care plan the task was scheduled from",
+        },
+        SearchParamDefinition {
+            name: "status",
+            param_type: "token",
+            documentation: "Task status, e.g. requested; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "priority",
+            param_type: "token",
+            documentation: "routine, urgent, asap or stat; comma-separated values match any",
+        },
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored; invalid
+    /// values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+
+        for (param, value) in params {
+            let (name, modifier) = split_modifier(param);
+            match (name, modifier) {
+                ("patient", None | Some("Patient")) => {
+                    for reference in value.split(',') {
+                        search.patient.push(reference_id(reference, "Patient")?);
+                    }
+                }
+                ("owner", None | Some("Practitioner")) => {
+                    for reference in value.split(',') {
+                        search.owner.push(reference_id(reference, "Practitioner")?);
+                    }
+                }
+                ("based-on", None | Some("CarePlan")) => {
+                    for reference in value.split(',') {
+                        search.based_on.push(reference_id(reference, "CarePlan")?);
+                    }
+                }
+                ("status", None) => {
+                    for code in value.split(',') {
+                        if TaskStatus::from_string(code).as_str() != code {
+                            return Err(invalid(name, code));
+                        }
+                        search.status.push(code.to_string());
+                    }
+                }
+                ("priority", None) => {
+                    for code in value.split(',') {
+                        if TaskPriority::from_string(code).as_str() != code {
+                            return Err(invalid(name, code));
+                        }
+                        search.priority.push(code.to_string());
+                    }
+                }
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported task search parameter {}", param),
+            }
+        }
+        search.page.check_cursor(&SEARCH_KEYS)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on tasks for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if !self.patient.is_empty() {
+            query.push(" AND patient_id = ANY(").push_bind(self.patient.clone()).push(")");
+        }
+        if !self.owner.is_empty() {
+            query.push(" AND owner_id = ANY(").push_bind(self.owner.clone()).push(")");
+        }
+        if !self.based_on.is_empty() {
+            query.push(" AND care_plan_id = ANY(").push_bind(self.based_on.clone()).push(")");
+        }
+        if !self.status.is_empty() {
+            query.push(" AND status = ANY(").push_bind(self.status.clone()).push(")");
+        }
+        if !self.priority.is_empty() {
+            query.push(" AND priority = ANY(").push_bind(self.priority.clone()).push(")");
+        }
+    }
+}
+
+/// Task service for work assigned to care-team members, whether scheduled
+/// from a care plan or created on its own
+pub struct TaskService {
+    pool: PgPool,
+    events: EventBus,
+    care_team: Arc<CareTeam>,
+}
+
+impl TaskService {
+    /// Create new task service
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_events(pool, EventBus::default())
+    }
+
+    /// Create the service publishing to a shared event bus
+    pub fn with_events(pool: PgPool, events: EventBus) -> Self {
+        let storage = Arc::new(PostgresAuthorizationStorage::new(pool.clone()));
+        Self {
+            pool,
+            events,
+            care_team: Arc::new(CareTeam::new(storage)),
+        }
+    }
+
+    /// Care teams tasks are assigned within
+    pub fn care_team(&self) -> Arc<CareTeam> {
+        self.care_team.clone()
+    }
+
+    /// Check the task's owner is on the care team of its patient or plan
+    async fn check_owner(&self, task: &Task) -> Result<(), HimsError> {
+        let Some(owner_id) = task.owner_id else {
+            return Ok(());
+        };
+        if !self.care_team.is_member(owner_id, task.patient_id, task.care_plan_id).await? {
+            return Err(HimsError::ValidationError {
+                message: format!("User {} is not on the care team of patient {}", owner_id, task.patient_id),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the task's care plan exists and is for the task's patient
+    async fn check_care_plan(&self, task: &Task) -> Result<(), HimsError> {
+        let Some(care_plan_id) = task.care_plan_id else {
+            return Ok(());
+        };
+        let patient_id: Option<Uuid> = sqlx::query_scalar(GET_CARE_PLAN_PATIENT)
+            .bind(care_plan_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        match patient_id {
+            Some(patient_id) if patient_id == task.patient_id => Ok(()),
+            Some(patient_id) => Err(HimsError::ValidationError {
+                message: format!("Care plan {} is for patient {}", care_plan_id, patient_id),
+            }),
+            None => Err(HimsError::ValidationError {
+                message: format!("Care plan {} does not exist", care_plan_id),
+            }),
+        }
+    }
+
+    fn bind_task<'q>(
+        query: sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>,
+        task: &'q Task,
+    ) -> sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments> {
+        query
+            .bind(task.id)
+            .bind(task.patient_id)
+            .bind(task.care_plan_id)
+            .bind(task.activity_id)
+            .bind(task.status.as_str())
+            .bind(&task.status_reason)
+            .bind(task.priority.as_str())
+            .bind(task.code.as_ref().and_then(|code| serde_json::to_value(code).ok()))
+            .bind(&task.description)
+            .bind(task.owner_id)
+            .bind(task.requester_id)
+            .bind(task.due)
+            .bind(task.authored_on)
+            .bind(task.last_modified)
+            .bind(&task.note)
+            .bind(task.created_at)
+            .bind(task.updated_at)
+            .bind(serde_json::to_value(&task.meta).unwrap_or_default())
+    }
+
+    /// Create a task
+    pub async fn create(&self, request: TaskRequest) -> Result<Task, HimsError> {
+        let task: Task = request.into();
+        check_task(&task)?;
+        self.check_care_plan(&task).await?;
+        self.check_owner(&task).await?;
+
+        Self::bind_task(sqlx::query(INSERT_TASK), &task)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.events.publish(DomainEvent::TaskCreated {
+            task_id: task.id,
+            patient_id: task.patient_id,
+        });
+        Ok(task)
+    }
+
+    /// Save tasks for care plan activity occurrences, skipping occurrences
+    /// that already have a task, and return the tasks created. The caller
+    /// has checked them and their owners.
+    pub async fn create_scheduled(&self, tasks: Vec<Task>) -> Result<Vec<Task>, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        let mut created = Vec::new();
+        for task in tasks {
+            let rows_affected = Self::bind_task(sqlx::query(INSERT_SCHEDULED_TASK), &task)
+                .execute(&mut *tx)
+                .await
+                .map_err(db)?
+                .rows_affected();
+            if rows_affected > 0 {
+                created.push(task);
+            }
+        }
+        tx.commit().await.map_err(db)?;
+
+        for task in &created {
+            self.events.publish(DomainEvent::TaskCreated {
+                task_id: task.id,
+                patient_id: task.patient_id,
+            });
+        }
+        Ok(created)
+    }
+
+    /// Get task by ID
+    pub async fn get(&self, id: Uuid) -> Result<Option<Task>, HimsError> {
+        let row = sqlx::query(GET_TASK_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(row.as_ref().map(task_from_row))
+    }
+
+    /// Search tasks, a page at a time
+    pub async fn search(&self, search: &TaskSearch) -> Result<TaskSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_TASKS);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &SEARCH_KEYS);
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut fetched = Vec::new();
+        for row in rows {
+            let task = task_from_row(&row);
+            fetched.push((task, RowKey::from_column(row.get("page_key"))?));
+        }
+        let page = search.page.page(fetched);
+
+        let total = count_matches(&self.pool, search.page.total(), COUNT_TASKS, ESTIMATE_TASKS, |query| {
+            search.push_filters(query)
+        })
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(TaskSearchResult {
+            tasks: page.items,
+            total,
+            next: page.next,
+            previous: page.previous,
+        })
+    }
+
+    /// Replace a task if it is still at `expected_version`, or `None` if
+    /// there is no such task. The status may only move along the FHIR task
+    /// state machine, and the task stays for the same patient and care plan
+    /// activity, which the request may leave out.
+    pub async fn update(&self, id: Uuid, request: TaskRequest, expected_version: &str) -> Result<Option<Task>, HimsError> {
+        let Some(current) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let current_version = current.meta.version_id.as_deref().unwrap_or("1");
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Task {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+        let moved = request.patient_id != current.patient_id
+            || request.care_plan_id.is_some_and(|care_plan_id| current.care_plan_id != Some(care_plan_id))
+            || request.activity_id.is_some_and(|activity_id| current.activity_id != Some(activity_id));
+        if moved {
+            return Err(HimsError::ValidationError {
+                message: format!("Task {} cannot move to another patient or care plan activity", id),
+            });
+        }
+        if !current.status.can_transition_to(request.status) {
+            return Err(HimsError::ValidationError {
+                message: format!(
+                    "Task {} cannot go from {} to {}",
+                    id,
+                    current.status.as_str(),
+                    request.status.as_str()
+                ),
+            });
+        }
+
+        let mut task: Task = request.into();
+        task.care_plan_id = current.care_plan_id;
+        task.activity_id = current.activity_id;
+        check_task(&task)?;
+        let reassigned = task.owner_id.filter(|owner_id| current.owner_id != Some(*owner_id));
+        if reassigned.is_some() {
+            self.check_owner(&task).await?;
+        }
+
+        let rows_affected = sqlx::query(UPDATE_TASK)
+            .bind(id)
+            .bind(task.status.as_str())
+            .bind(&task.status_reason)
+            .bind(task.priority.as_str())
+            .bind(task.code.as_ref().and_then(|code| serde_json::to_value(code).ok()))
+            .bind(&task.description)
+            .bind(task.owner_id)
+            .bind(task.requester_id)
+            .bind(task.due)
+            .bind(&task.note)
+            .bind(Utc::now())
+            .bind(expected_version)
+            .bind(next_version_id(Some(expected_version)))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Task {} was modified concurrently", id),
+            });
+        }
+        self.events.publish(DomainEvent::TaskUpdated { task_id: id });
+        if let Some(owner_id) = reassigned {
+            self.events.publish(DomainEvent::TaskAssigned { task_id: id, owner_id });
+        }
+
+        self.get(id).await
+    }
+
+    /// Give an open task to a member of its patient's or care plan's care
+    /// team, or `None` if there is no such task
+    pub async fn assign(&self, id: Uuid, owner_id: Uuid) -> Result<Option<Task>, HimsError> {
+        let Some(mut task) = self.get(id).await? else {
+            return Ok(None);
+        };
+        if task.status.is_terminal() {
+            return Err(HimsError::ValidationError {
+                message: format!("Task {} is {} and cannot be reassigned", id, task.status.as_str()),
+            });
+        }
+        task.owner_id = Some(owner_id);
+        self.check_owner(&task).await?;
+
+        sqlx::query(ASSIGN_TASK)
+            .bind(id)
+            .bind(owner_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        self.events.publish(DomainEvent::TaskAssigned { task_id: id, owner_id });
+
+        self.get(id).await
+    }
+
+    /// Cancel the open tasks scheduled from a care plan, or from the given
+    /// activities of it, and return their IDs
+    pub async fn cancel_open_for_plan(
+        &self,
+        care_plan_id: Uuid,
+        activity_ids: Option<Vec<Uuid>>,
+        reason: &str,
+    ) -> Result<Vec<Uuid>, HimsError> {
+        let cancelled: Vec<Uuid> = sqlx::query_scalar(CANCEL_OPEN_PLAN_TASKS)
+            .bind(care_plan_id)
+            .bind(activity_ids)
+            .bind(reason)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        for task_id in &cancelled {
+            self.events.publish(DomainEvent::TaskUpdated { task_id: *task_id });
+        }
+        Ok(cancelled)
+    }
+
+    /// Soft delete task
+    pub async fn delete(&self, id: Uuid) -> Result<bool, HimsError> {
+        let rows_affected = sqlx::query(SOFT_DELETE_TASK)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected > 0 {
+            self.events.publish(DomainEvent::TaskDeleted { task_id: id });
+        }
+        Ok(rows_affected > 0)
+    }
+
+    /// Open tasks past their due date at `now`, optionally only an owner's
+    /// or a patient's, the longest overdue first
+    pub async fn overdue(
+        &self,
+        now: DateTime<Utc>,
+        owner_id: Option<Uuid>,
+        patient_id: Option<Uuid>,
+    ) -> Result<Vec<Task>, HimsError> {
+        let rows = sqlx::query(GET_OVERDUE_TASKS)
+            .bind(now)
+            .bind(owner_id)
+            .bind(patient_id)
+            .bind(MAX_OVERDUE_TASKS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(rows.iter().map(task_from_row).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_status_workflow() {
+        assert!(TaskStatus::Requested.can_transition_to(TaskStatus::Accepted));
+        assert!(TaskStatus::Accepted.can_transition_to(TaskStatus::InProgress));
+        assert!(TaskStatus::InProgress.can_transition_to(TaskStatus::Completed));
+        assert!(TaskStatus::Completed.can_transition_to(TaskStatus::EnteredInError));
+        assert!(!TaskStatus::Completed.can_transition_to(TaskStatus::InProgress));
+        assert!(!TaskStatus::Requested.can_transition_to(TaskStatus::Completed));
+        for status in TaskStatus::ALL {
+            assert_eq!(TaskStatus::from_string(status.as_str()), status);
+        }
+
+        let mut task = Task::new(Uuid::new_v4());
+        assert!(check_task(&task).is_err(), "nothing to do");
+        task.description = Some("Check blood glucose".to_string());
+        assert!(check_task(&task).is_ok());
+        task.activity_id = Some(Uuid::new_v4());
+        assert!(check_task(&task).is_err(), "activity without its plan");
+
+        let now = Utc::now();
+        task.due = Some(now - chrono::Duration::hours(1));
+        assert!(task.is_overdue(now));
+        task.status = TaskStatus::Completed;
+        assert!(!task.is_overdue(now));
+    }
+
+    #[test]
+    fn test_search_from_params() {
+        let owner = Uuid::new_v4();
+        let plan = Uuid::new_v4();
+        let search = TaskSearch::from_params(&params(&[
+            ("owner", format!("Practitioner/{}", owner).as_str()),
+            ("based-on", format!("CarePlan/{}", plan).as_str()),
+            ("status", "requested,in-progress"),
+        ]))
+        .unwrap();
+        assert_eq!(search.owner, vec![owner]);
+        assert_eq!(search.based_on, vec![plan]);
+        assert_eq!(search.status, vec!["requested".to_string(), "in-progress".to_string()]);
+
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM tasks WHERE deleted_at IS NULL");
+        search.push_filters(&mut query);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM tasks WHERE deleted_at IS NULL AND owner_id = ANY($1) AND care_plan_id = ANY($2) AND status = ANY($3)"
+        );
+
+        assert!(TaskSearch::from_params(&params(&[("status", "done")])).is_err());
+        assert!(TaskSearch::from_params(&params(&[("based-on", "Condition/1")])).is_err());
+    }
+
+    #[test]
+    fn test_advertised_params_are_supported() {
+        let id = Uuid::new_v4();
+        for param in TaskSearch::SEARCH_PARAMS {
+            let value = match param.name {
+                "status" => "ready".to_string(),
+                "priority" => "stat".to_string(),
+                _ => id.to_string(),
+            };
+            let search = TaskSearch::from_params(&params(&[(param.name, value.as_str())])).unwrap();
+            assert_ne!(search, TaskSearch::default(), "{} was ignored", param.name);
+        }
+    }
+}
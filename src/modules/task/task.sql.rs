@@ -0,0 +1,120 @@
+//! Task SQL Queries
+//! 
+//! This file contains all SQL queries used by the task service.
+
+/// Insert a new task
+pub const INSERT_TASK: &str = r#"
+    INSERT INTO tasks (
+        id, patient_id, care_plan_id, activity_id, status, status_reason, priority, code,
+        description, owner_id, requester_id, due, authored_on, last_modified, note,
+        created_at, updated_at, meta
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18
+    )
+"#;
+
+/// Insert a task for an occurrence of a care plan activity unless the
+/// occurrence already has one; inserts no row then
+pub const INSERT_SCHEDULED_TASK: &str = r#"
+    INSERT INTO tasks (
+        id, patient_id, care_plan_id, activity_id, status, status_reason, priority, code,
+        description, owner_id, requester_id, due, authored_on, last_modified, note,
+        created_at, updated_at, meta
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18
+    )
+    ON CONFLICT (care_plan_id, activity_id, due) DO NOTHING
+"#;
+
+/// Get task by ID
+pub const GET_TASK_BY_ID: &str = r#"
+    SELECT id, patient_id, care_plan_id, activity_id, status, status_reason, priority, code,
+           description, owner_id, requester_id, due, authored_on, last_modified, note,
+           created_at, updated_at, meta
+    FROM tasks
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Replace a task if it is still at version $12, moving it to version $13.
+/// The patient and the care plan activity a task is for never change.
+pub const UPDATE_TASK: &str = r#"
+    UPDATE tasks
+    SET status = $2, status_reason = $3, priority = $4, code = $5, description = $6,
+        owner_id = $7, requester_id = $8, due = $9, note = $10, last_modified = $11,
+        updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($13::text))
+    WHERE id = $1 AND deleted_at IS NULL AND COALESCE(meta->>'version_id', '1') = $12
+"#;
+
+/// Give a task to owner $2, moving it to the next version
+pub const ASSIGN_TASK: &str = r#"
+    UPDATE tasks
+    SET owner_id = $2, last_modified = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Cancel the open tasks scheduled from care plan $1, or from its
+/// activities $2 when that is not NULL, giving reason $3
+pub const CANCEL_OPEN_PLAN_TASKS: &str = r#"
+    UPDATE tasks
+    SET status = 'cancelled', status_reason = $3, last_modified = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE care_plan_id = $1 AND deleted_at IS NULL
+      AND ($2::uuid[] IS NULL OR activity_id = ANY($2))
+      AND status NOT IN ('rejected', 'cancelled', 'failed', 'completed', 'entered-in-error')
+    RETURNING id
+"#;
+
+/// Soft delete task
+pub const SOFT_DELETE_TASK: &str = r#"
+    UPDATE tasks
+    SET deleted_at = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Open tasks due before $1, owned by $2 and for patient $3 where those are
+/// not NULL, the longest overdue first
+pub const GET_OVERDUE_TASKS: &str = r#"
+    SELECT id, patient_id, care_plan_id, activity_id, status, status_reason, priority, code,
+           description, owner_id, requester_id, due, authored_on, last_modified, note,
+           created_at, updated_at, meta
+    FROM tasks
+    WHERE due < $1 AND deleted_at IS NULL
+      AND status NOT IN ('rejected', 'cancelled', 'failed', 'completed', 'entered-in-error')
+      AND ($2::uuid IS NULL OR owner_id = $2)
+      AND ($3::uuid IS NULL OR patient_id = $3)
+    ORDER BY due, id
+    LIMIT $4
+"#;
+
+/// Start of a task search, soonest due first and undated tasks last; the
+/// service appends the filters and the page's cursor, order and limit
+pub const SEARCH_TASKS: &str = r#"
+    SELECT id, patient_id, care_plan_id, activity_id, status, status_reason, priority, code,
+           description, owner_id, requester_id, due, authored_on, last_modified, note,
+           created_at, updated_at, meta,
+           ARRAY[COALESCE(due, 'infinity')::text, id::text] AS page_key
+    FROM tasks
+    WHERE deleted_at IS NULL
+"#;
+
+/// Start of an accurate task search total
+pub const COUNT_TASKS: &str = r#"
+    SELECT COUNT(*) FROM tasks WHERE deleted_at IS NULL
+"#;
+
+/// Query plan of a task search, whose row estimate answers `_total=estimate`
+pub const ESTIMATE_TASKS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM tasks WHERE deleted_at IS NULL
+"#;
+
+/// Patient of a care plan, to check the tasks scheduled from it are for the
+/// same patient
+pub const GET_CARE_PLAN_PATIENT: &str = r#"
+    SELECT patient_id FROM care_plans WHERE id = $1 AND deleted_at IS NULL
+"#;
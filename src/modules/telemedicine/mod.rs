@@ -0,0 +1,68 @@
+//! Telemedicine Module
+//!
+//! This module holds video visits including:
+//! - Visits scheduled, started and finished as FHIR Encounters of the virtual class
+//! - The telehealth rules of the deployment's state, from the US state registry
+//! - Cross-state practice checked against the practitioner's licenses
+//! - Prescriptions checked against the state's restrictions before they are written
+//! - The patient's consent to recording, without which a visit is not recorded
+//! - Short-lived Twilio or Jitsi session tokens for participants the authorization engine admits
+//! - Participants joining and leaving, logged against the session they joined through
+
+#[path = "telemedicine.controller.rs"]
+pub mod telemedicine_controller;
+#[path = "telemedicine.service.rs"]
+pub mod telemedicine_service;
+#[path = "telemedicine.rules.rs"]
+pub mod telemedicine_rules;
+#[path = "telemedicine.video.rs"]
+pub mod telemedicine_video;
+#[path = "telemedicine.sql.rs"]
+pub mod telemedicine_sql;
+
+pub use telemedicine_controller::TelemedicineController;
+pub use telemedicine_rules::{ProposedPrescription, TelemedicineRules, VisitContext};
+pub use telemedicine_service::{
+    RecordingConsent, SessionEvent, TelemedicineConfig, TelemedicineService, TelemedicineVisit, VideoSession,
+    VisitStatus,
+};
+pub use telemedicine_video::{ParticipantRole, VideoGrant, VideoProvider, VideoProviderConfig, VideoToken};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::utils::api_router::ApiRouter;
+
+/// Telemedicine Module Configuration
+pub struct TelemedicineModule {
+    pub service: Arc<TelemedicineService>,
+    pub controller: Arc<TelemedicineController>,
+}
+
+impl TelemedicineModule {
+    /// Create a new Telemedicine Module with dependency injection
+    pub fn new(
+        db_pool: PgPool,
+        config: TelemedicineConfig,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+    ) -> Self {
+        let service = Arc::new(TelemedicineService::new(db_pool, config, authorization_engine));
+        let controller = Arc::new(TelemedicineController::new(service.clone()));
+
+        Self {
+            service,
+            controller,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<TelemedicineService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,307 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::SessionContext;
+use crate::modules::telemedicine::telemedicine_rules::{ProposedPrescription, TelemedicineRules};
+use crate::modules::telemedicine::telemedicine_service::{
+    PrescriptionDecision, RecordingConsentRequest, RecordingRequest, SessionEvent, SessionEventRequest, SessionRequest,
+    StatusRequest, TelemedicineVisit, VideoSession, VisitRequest,
+};
+use crate::modules::telemedicine::TelemedicineService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Telemedicine controller for video visits, their recording consent and
+/// the sessions participants join them through
+pub struct TelemedicineController {
+    telemedicine_service: Arc<TelemedicineService>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+impl TelemedicineController {
+    /// Create new controller with injected service
+    pub fn new(telemedicine_service: Arc<TelemedicineService>) -> Self {
+        Self { telemedicine_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/visits", Self::schedule, "Schedule a video visit")
+            .get("/rules", Self::rules, "Get the telehealth rules visits follow")
+            .get("/patients/:patient_id/visits", Self::list_for_patient, "List a patient's video visits")
+            .get("/visits/:id", Self::get, "Get a video visit")
+            .get("/visits/:id/encounter", Self::encounter, "Get a video visit as a FHIR Encounter")
+            .post("/visits/:id/status", Self::move_to, "Start, finish or cancel a video visit")
+            .put("/visits/:id/recording-consent", Self::record_consent, "Record consent to recording a visit")
+            .post("/visits/:id/recording", Self::set_recording, "Start or stop recording a video visit")
+            .post(
+                "/visits/:id/prescriptions/check",
+                Self::check_prescription,
+                "Check a prescription against the telehealth rules",
+            )
+            .post("/visits/:id/sessions", Self::issue_session, "Issue a session token for joining a video visit")
+            .post(
+                "/visits/:id/sessions/:session_id/events",
+                Self::record_session_event,
+                "Log a participant joining or leaving a video visit",
+            )
+            .get("/visits/:id/events", Self::list_session_events, "List a video visit's join and leave events")
+            .with_state(self.telemedicine_service.clone())
+    }
+
+    /// Schedule a video visit
+    pub async fn schedule(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Json(payload): Json<VisitRequest>,
+    ) -> Result<(StatusCode, Json<TelemedicineVisit>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.schedule(payload, actor).await {
+            Ok(visit) => Ok((StatusCode::CREATED, Json(visit))),
+            Err(e) => Err(Self::error_response("Failed to schedule video visit", e)),
+        }
+    }
+
+    /// Get the telehealth rules visits follow
+    pub async fn rules(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<TelemedicineRules>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.rules(actor).await {
+            Ok(rules) => Ok(Json(rules)),
+            Err(e) => Err(Self::error_response("Failed to retrieve telehealth rules", e)),
+        }
+    }
+
+    /// List a patient's video visits, latest first
+    pub async fn list_for_patient(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(patient_id): Path<Uuid>,
+    ) -> Result<Json<Vec<TelemedicineVisit>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_for_patient(patient_id, actor).await {
+            Ok(visits) => Ok(Json(visits)),
+            Err(e) => Err(Self::error_response("Failed to list video visits", e)),
+        }
+    }
+
+    /// Get a video visit
+    pub async fn get(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<TelemedicineVisit>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.get(id, actor).await {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Video visit", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve video visit", e)),
+        }
+    }
+
+    /// Get a video visit as a FHIR Encounter
+    pub async fn encounter(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Value>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.get(id, actor).await {
+            Ok(Some(visit)) => Ok(Json(visit.to_encounter())),
+            Ok(None) => Err(Self::not_found("Video visit", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve video visit", e)),
+        }
+    }
+
+    /// Start, finish or cancel a video visit
+    pub async fn move_to(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<StatusRequest>,
+    ) -> Result<Json<TelemedicineVisit>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.move_to(id, payload.status, actor).await {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Video visit", id)),
+            Err(e) => Err(Self::error_response("Failed to update video visit", e)),
+        }
+    }
+
+    /// Record the patient's consent or refusal to a visit being recorded
+    pub async fn record_consent(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RecordingConsentRequest>,
+    ) -> Result<Json<TelemedicineVisit>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.record_consent(id, payload, actor).await {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Video visit", id)),
+            Err(e) => Err(Self::error_response("Failed to record recording consent", e)),
+        }
+    }
+
+    /// Start or stop recording a video visit
+    pub async fn set_recording(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<RecordingRequest>,
+    ) -> Result<Json<TelemedicineVisit>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.set_recording(id, payload.recording, actor).await {
+            Ok(Some(visit)) => Ok(Json(visit)),
+            Ok(None) => Err(Self::not_found("Video visit", id)),
+            Err(e) => Err(Self::error_response("Failed to change video visit recording", e)),
+        }
+    }
+
+    /// Check a prescription against the telehealth rules before writing it
+    pub async fn check_prescription(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<ProposedPrescription>,
+    ) -> Result<Json<PrescriptionDecision>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.check_prescription(id, payload, actor).await {
+            Ok(Some(decision)) => Ok(Json(decision)),
+            Ok(None) => Err(Self::not_found("Video visit", id)),
+            Err(e) => Err(Self::error_response("Failed to check prescription", e)),
+        }
+    }
+
+    /// Issue a session token for joining a video visit
+    pub async fn issue_session(
+        State(service): State<Arc<TelemedicineService>>,
+        auth: AuthContext,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<SessionRequest>,
+    ) -> Result<(StatusCode, Json<VideoSession>), ErrorReply> {
+        match service.issue_session(id, payload, Self::session(&auth)).await {
+            Ok(Some(session)) => Ok((StatusCode::CREATED, Json(session))),
+            Ok(None) => Err(Self::not_found("Video visit", id)),
+            Err(e) => Err(Self::error_response("Failed to issue video session", e)),
+        }
+    }
+
+    /// Log a participant joining or leaving a video visit
+    pub async fn record_session_event(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path((id, session_id)): Path<(Uuid, Uuid)>,
+        Json(payload): Json<SessionEventRequest>,
+    ) -> Result<(StatusCode, Json<SessionEvent>), ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.record_session_event(id, session_id, payload.event, actor).await {
+            Ok(Some(event)) => Ok((StatusCode::CREATED, Json(event))),
+            Ok(None) => Err(Self::not_found("Video session", session_id)),
+            Err(e) => Err(Self::error_response("Failed to log video session event", e)),
+        }
+    }
+
+    /// List a video visit's join and leave events
+    pub async fn list_session_events(
+        State(service): State<Arc<TelemedicineService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<SessionEvent>>, ErrorReply> {
+        let actor = Self::actor(&headers)?;
+
+        match service.list_session_events(id, actor).await {
+            Ok(events) => Ok(Json(events)),
+            Err(e) => Err(Self::error_response("Failed to list video session events", e)),
+        }
+    }
+
+    /// Session of the request, for the authorization engine. The session and
+    /// address are the authenticated ones, never headers a client could set.
+    fn session(auth: &AuthContext) -> SessionContext {
+        SessionContext {
+            user_id: auth.user_id,
+            session_id: auth.session_id.clone().unwrap_or_else(|| "unknown".to_string()),
+            ip_address: auth.source_ip.clone(),
+            user_agent: auth.user_agent.clone(),
+            department_id: None,
+            location_id: None,
+            shift_id: None,
+            mfa_verified: false,
+            mfa_verified_at: None,
+            risk_score: 0.0,
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, ErrorReply> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(what: &str, id: Uuid) -> ErrorReply {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", what),
+                message: format!("{} with id {} not found", what, id),
+            }),
+        )
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
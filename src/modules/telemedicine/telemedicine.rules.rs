@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::HimsError;
+use crate::countries::usa::states::{TelemedicineRegulations, UsStateRegistry};
+
+/// A state's telehealth rules, with its prescription restrictions read
+/// from the text of its regulations
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemedicineRules {
+    /// State whose rules these are; `None` where no state is configured
+    pub state: Option<String>,
+    pub allowed: bool,
+    /// Practitioners may see patients located in other states
+    pub cross_state_practice: bool,
+    /// Controlled substances are prescribed only after an in-person exam,
+    /// as under the Ryan Haight Act
+    pub controlled_substances_need_in_person: bool,
+    /// Controlled substances are prescribed only by DEA registrants
+    pub dea_registration_required: bool,
+    /// A patient's first consultation with a practitioner is in person
+    pub initial_consultation_in_person: bool,
+    /// Prescribing needs a patient-practitioner relationship established
+    /// by an earlier visit
+    pub established_relationship_required: bool,
+    pub prescription_restrictions: Vec<String>,
+    pub required_standards: Vec<String>,
+}
+
+/// What a visit's rules are checked against
+#[derive(Debug, Clone, Default)]
+pub struct VisitContext<'a> {
+    /// State the patient is located in during the visit
+    pub patient_state: Option<&'a str>,
+    /// States the practitioner is licensed in
+    pub practitioner_licenses: &'a [String],
+    /// The practitioner has seen the patient in person before
+    pub prior_in_person: bool,
+    /// The practitioner has seen the patient before, in person or not
+    pub prior_visit: bool,
+}
+
+/// A prescription a practitioner means to write during a visit
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProposedPrescription {
+    pub medication: String,
+    /// DEA schedule, II to V, of a controlled substance
+    pub controlled_schedule: Option<String>,
+    pub prescriber_dea_number: Option<String>,
+}
+
+impl ProposedPrescription {
+    pub fn is_controlled(&self) -> bool {
+        self.controlled_schedule.as_deref().is_some_and(|schedule| !schedule.trim().is_empty())
+    }
+}
+
+impl TelemedicineRules {
+    /// Rules of a state in the US state registry
+    pub fn for_state(state: &str) -> Result<Self, HimsError> {
+        let state = state.trim().to_ascii_uppercase();
+        let registry = UsStateRegistry::new();
+        let config = registry.get_state_config(&state)?;
+        Ok(Self::from_regulations(&state, &config.telemedicine_regulations))
+    }
+
+    /// Read a state's regulations, picking out the prescription
+    /// restrictions these rules enforce from their wording
+    pub fn from_regulations(state: &str, regulations: &TelemedicineRegulations) -> Self {
+        let mut rules = Self {
+            state: Some(state.to_string()),
+            allowed: regulations.allowed,
+            cross_state_practice: regulations.cross_state_practice,
+            controlled_substances_need_in_person: false,
+            dea_registration_required: false,
+            initial_consultation_in_person: false,
+            established_relationship_required: false,
+            prescription_restrictions: regulations.prescription_restrictions.clone(),
+            required_standards: regulations.required_standards.clone(),
+        };
+        for restriction in &regulations.prescription_restrictions {
+            let text = restriction.to_lowercase();
+            let in_person = text.contains("in-person") || text.contains("in person");
+            if text.contains("ryan haight") || (text.contains("controlled substance") && in_person) {
+                rules.controlled_substances_need_in_person = true;
+            }
+            if text.contains("dea registration") {
+                rules.dea_registration_required = true;
+            }
+            if text.contains("initial") && in_person {
+                rules.initial_consultation_in_person = true;
+            }
+            if text.contains("relationship") && text.contains("established") {
+                rules.established_relationship_required = true;
+            }
+        }
+        rules
+    }
+
+    /// Rules where no state is configured: telehealth is allowed and only
+    /// recording consent is enforced
+    pub fn unrestricted() -> Self {
+        Self {
+            state: None,
+            allowed: true,
+            cross_state_practice: true,
+            controlled_substances_need_in_person: false,
+            dea_registration_required: false,
+            initial_consultation_in_person: false,
+            established_relationship_required: false,
+            prescription_restrictions: Vec::new(),
+            required_standards: Vec::new(),
+        }
+    }
+
+    /// Rules of a configured state that could not be found, under which
+    /// no visit is allowed
+    pub fn unavailable(state: &str) -> Self {
+        Self {
+            state: Some(state.to_string()),
+            allowed: false,
+            cross_state_practice: false,
+            ..Self::unrestricted()
+        }
+    }
+
+    /// Reasons a video visit may not take place; empty if it may
+    pub fn check_visit(&self, context: &VisitContext) -> Vec<String> {
+        let mut violations = Vec::new();
+        let Some(state) = self.state.as_deref() else {
+            return violations;
+        };
+        if !self.allowed {
+            violations.push(format!("Telemedicine is not permitted under the rules of {}", state));
+            return violations;
+        }
+
+        match context.patient_state {
+            None => violations.push("The patient's state is needed to check cross-state practice".to_string()),
+            Some(patient_state) if !patient_state.eq_ignore_ascii_case(state) => {
+                let licensed = context
+                    .practitioner_licenses
+                    .iter()
+                    .any(|license| license.eq_ignore_ascii_case(patient_state));
+                if !self.cross_state_practice && !licensed {
+                    violations.push(format!(
+                        "{} does not permit cross-state practice and the practitioner is not licensed in {}",
+                        state, patient_state
+                    ));
+                }
+            }
+            Some(_) => {}
+        }
+
+        if self.initial_consultation_in_person && !context.prior_in_person {
+            violations.push(format!("{} requires the initial consultation to be in person", state));
+        }
+        violations
+    }
+
+    /// Reasons a prescription may not be written during a video visit;
+    /// empty if it may
+    pub fn check_prescription(&self, context: &VisitContext, prescription: &ProposedPrescription) -> Vec<String> {
+        let mut violations = Vec::new();
+        let Some(state) = self.state.as_deref() else {
+            return violations;
+        };
+
+        if self.established_relationship_required && !context.prior_visit {
+            violations.push(format!(
+                "{} requires a patient-practitioner relationship to be established before prescribing",
+                state
+            ));
+        }
+        if prescription.is_controlled() {
+            if self.controlled_substances_need_in_person && !context.prior_in_person {
+                violations.push(format!(
+                    "{} is a controlled substance, which needs an in-person exam before it is prescribed",
+                    prescription.medication
+                ));
+            }
+            let dea_number = prescription.prescriber_dea_number.as_deref().map(str::trim).unwrap_or("");
+            if self.dea_registration_required && dea_number.is_empty() {
+                violations.push(format!("{} requires a DEA registration to prescribe controlled substances", state));
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regulations(cross_state_practice: bool, restrictions: &[&str]) -> TelemedicineRegulations {
+        TelemedicineRegulations {
+            allowed: true,
+            cross_state_practice,
+            prescription_restrictions: restrictions.iter().map(|r| r.to_string()).collect(),
+            required_standards: Vec::new(),
+        }
+    }
+
+    fn controlled() -> ProposedPrescription {
+        ProposedPrescription {
+            medication: "Oxycodone 5 mg".to_string(),
+            controlled_schedule: Some("II".to_string()),
+            prescriber_dea_number: None,
+        }
+    }
+
+    #[test]
+    fn restrictions_are_read_from_their_wording() {
+        let rules = TelemedicineRules::from_regulations(
+            "TX",
+            &regulations(
+                false,
+                &[
+                    "Ryan Haight Act limits on controlled substance prescribing",
+                    "DEA registration required for controlled substances",
+                    "Patient-physician relationship must be established",
+                ],
+            ),
+        );
+        assert!(rules.controlled_substances_need_in_person);
+        assert!(rules.dea_registration_required);
+        assert!(rules.established_relationship_required);
+        assert!(!rules.initial_consultation_in_person);
+
+        let context = VisitContext { patient_state: Some("TX"), ..Default::default() };
+        assert_eq!(rules.check_prescription(&context, &controlled()).len(), 3);
+
+        let context = VisitContext {
+            patient_state: Some("TX"),
+            prior_in_person: true,
+            prior_visit: true,
+            ..Default::default()
+        };
+        let mut prescription = controlled();
+        prescription.prescriber_dea_number = Some("AB1234563".to_string());
+        assert!(rules.check_prescription(&context, &prescription).is_empty());
+    }
+
+    #[test]
+    fn cross_state_visits_need_a_license_where_the_patient_is() {
+        let rules = TelemedicineRules::from_regulations("CA", &regulations(false, &[]));
+        let licenses = vec!["CA".to_string()];
+        let context = VisitContext {
+            patient_state: Some("NV"),
+            practitioner_licenses: &licenses,
+            ..Default::default()
+        };
+        assert_eq!(rules.check_visit(&context).len(), 1);
+
+        let licenses = vec!["CA".to_string(), "nv".to_string()];
+        let context = VisitContext {
+            patient_state: Some("NV"),
+            practitioner_licenses: &licenses,
+            ..Default::default()
+        };
+        assert!(rules.check_visit(&context).is_empty());
+
+        let compact = TelemedicineRules::from_regulations("CA", &regulations(true, &[]));
+        let context = VisitContext { patient_state: Some("NV"), ..Default::default() };
+        assert!(compact.check_visit(&context).is_empty());
+        assert_eq!(TelemedicineRules::unavailable("ZZ").check_visit(&context).len(), 1);
+    }
+}
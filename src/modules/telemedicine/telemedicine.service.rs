@@ -0,0 +1,993 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{
+    ACT_CODE_SYSTEM, ENCOUNTER_RESOURCE_TYPE, SNOMED_CT_SYSTEM, TELEMEDICINE_CONSULTATION_CODE,
+    VIRTUAL_ENCOUNTER_CLASS,
+};
+use crate::modules::authorization::{
+    AccessDecision, Action, AuthorizationEngine, AuthorizationRequest, AuthorizationResponse, Consistency,
+    HimsAuthorizationEngine, PurposeOfUse, RequestContext, Resource, SessionContext, Subject,
+};
+use crate::modules::role::RoleService;
+use crate::modules::telemedicine::telemedicine_rules::{ProposedPrescription, TelemedicineRules, VisitContext};
+use crate::modules::telemedicine::telemedicine_video::{ParticipantRole, VideoGrant, VideoProvider, VideoProviderConfig};
+
+// Import SQL queries
+use crate::modules::telemedicine::telemedicine_sql::*;
+
+/// Telemedicine settings
+#[derive(Debug, Clone)]
+pub struct TelemedicineConfig {
+    /// US state whose telehealth rules visits follow; none outside the US
+    pub state: Option<String>,
+    /// Base URL of the video service, whose rooms are joined at
+    /// `<url>/<room>`
+    pub video_base_url: Option<String>,
+    /// Video service session tokens are issued by
+    pub video_provider: Option<VideoProviderConfig>,
+    /// Minutes a session token may be used to join a room for
+    pub session_ttl_minutes: i64,
+}
+
+impl Default for TelemedicineConfig {
+    fn default() -> Self {
+        Self {
+            state: None,
+            video_base_url: None,
+            video_provider: None,
+            session_ttl_minutes: 15,
+        }
+    }
+}
+
+impl TelemedicineConfig {
+    /// Settings from `TELEMEDICINE_STATE`, a US state code,
+    /// `TELEMEDICINE_VIDEO_URL`, `TELEMEDICINE_SESSION_TTL_MINUTES` and the
+    /// video provider's credentials
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            state: var("TELEMEDICINE_STATE").map(|state| state.trim().to_ascii_uppercase()),
+            video_base_url: var("TELEMEDICINE_VIDEO_URL").map(|url| url.trim_end_matches('/').to_string()),
+            video_provider: VideoProviderConfig::from_env(),
+            session_ttl_minutes: var("TELEMEDICINE_SESSION_TTL_MINUTES")
+                .and_then(|minutes| minutes.parse().ok())
+                .filter(|minutes| (1..=240).contains(minutes))
+                .unwrap_or(Self::default().session_ttl_minutes),
+        }
+    }
+}
+
+/// Where a video visit is, as the status of the encounter it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VisitStatus {
+    Planned,
+    InProgress,
+    Finished,
+    Cancelled,
+}
+
+impl VisitStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VisitStatus::Planned => "planned",
+            VisitStatus::InProgress => "in-progress",
+            VisitStatus::Finished => "finished",
+            VisitStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "planned" => Some(VisitStatus::Planned),
+            "in-progress" => Some(VisitStatus::InProgress),
+            "finished" => Some(VisitStatus::Finished),
+            "cancelled" => Some(VisitStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// A patient's consent, or refusal, to a visit being recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingConsent {
+    pub given: bool,
+    /// Who consented, if not the patient, such as a parent or guardian
+    pub consenter: Option<String>,
+    /// How consent was taken, such as verbal or written
+    pub method: Option<String>,
+    pub recorded_by: Uuid,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A video visit between a patient and a practitioner
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemedicineVisit {
+    pub id: Uuid,
+    /// The virtual encounter the visit is
+    pub encounter_id: Uuid,
+    pub patient_id: Uuid,
+    pub practitioner_id: Uuid,
+    pub reason: Option<String>,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: Option<DateTime<Utc>>,
+    /// State the patient is located in during the visit
+    pub patient_state: Option<String>,
+    /// States the practitioner is licensed in
+    pub practitioner_licenses: Vec<String>,
+    pub status: VisitStatus,
+    pub video_room: String,
+    pub join_url: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub recording_consent: Option<RecordingConsent>,
+    /// When the visit began being recorded; `None` while it is not
+    pub recording_started_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TelemedicineVisit {
+    /// Move the visit to a status: planned visits start or are cancelled,
+    /// and visits in progress finish, which stops any recording
+    pub fn move_to(&mut self, status: VisitStatus, now: DateTime<Utc>) -> Result<(), String> {
+        match (self.status, status) {
+            (VisitStatus::Planned, VisitStatus::InProgress) => self.started_at = Some(now),
+            (VisitStatus::Planned, VisitStatus::Cancelled) => {}
+            (VisitStatus::InProgress, VisitStatus::Finished) => {
+                self.ended_at = Some(now);
+                self.recording_started_at = None;
+            }
+            (from, to) => {
+                return Err(format!("Visit {} cannot move from {} to {}", self.id, from.as_str(), to.as_str()));
+            }
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    /// Take the patient's consent or refusal to recording; a refusal stops
+    /// any recording
+    pub fn record_consent(&mut self, consent: RecordingConsent) -> Result<(), String> {
+        if matches!(self.status, VisitStatus::Finished | VisitStatus::Cancelled) {
+            return Err(format!("Visit {} is {}", self.id, self.status.as_str()));
+        }
+        if !consent.given {
+            self.recording_started_at = None;
+        }
+        self.recording_consent = Some(consent);
+        Ok(())
+    }
+
+    /// Start or stop recording; a visit is recorded only while in progress
+    /// and with the patient's consent
+    pub fn set_recording(&mut self, recording: bool, now: DateTime<Utc>) -> Result<(), String> {
+        if !recording {
+            self.recording_started_at = None;
+            return Ok(());
+        }
+        if self.status != VisitStatus::InProgress {
+            return Err(format!("Visit {} is recorded only while in progress", self.id));
+        }
+        if !self.recording_consent.as_ref().is_some_and(|consent| consent.given) {
+            return Err(format!("The patient has not consented to visit {} being recorded", self.id));
+        }
+        self.recording_started_at.get_or_insert(now);
+        Ok(())
+    }
+
+    /// The visit as a FHIR Encounter of the virtual class
+    pub fn to_encounter(&self) -> Value {
+        let mut encounter = json!({
+            "resourceType": ENCOUNTER_RESOURCE_TYPE,
+            "id": self.encounter_id,
+            "status": self.status.as_str(),
+            "class": virtual_class(),
+            "serviceType": telemedicine_service_type(),
+            "subject": { "reference": format!("Patient/{}", self.patient_id) },
+            "participant": practitioner_participant(self.practitioner_id),
+            "period": self.period(),
+            "meta": { "lastUpdated": self.updated_at },
+        });
+        if let Some(reason) = &self.reason {
+            encounter["reasonCode"] = reason_code(reason);
+        }
+        encounter
+    }
+
+    /// When the visit took place, or is to
+    fn period(&self) -> Value {
+        let mut period = json!({ "start": self.started_at.unwrap_or(self.scheduled_start) });
+        if let Some(end) = self.ended_at.or(self.scheduled_end) {
+            period["end"] = json!(end);
+        }
+        period
+    }
+}
+
+/// HL7 ActCode "VR", the class of every video visit
+fn virtual_class() -> Value {
+    json!({ "system": ACT_CODE_SYSTEM, "code": VIRTUAL_ENCOUNTER_CLASS, "display": "virtual" })
+}
+
+fn telemedicine_service_type() -> Value {
+    json!({
+        "coding": [{
+            "system": SNOMED_CT_SYSTEM,
+            "code": TELEMEDICINE_CONSULTATION_CODE,
+            "display": "Telemedicine consultation with patient",
+        }]
+    })
+}
+
+fn practitioner_participant(practitioner_id: Uuid) -> Value {
+    json!([{ "individual": { "reference": format!("Practitioner/{}", practitioner_id) } }])
+}
+
+fn reason_code(reason: &str) -> Value {
+    json!([{ "text": reason }])
+}
+
+/// A video visit to schedule
+#[derive(Debug, Clone, Deserialize)]
+pub struct VisitRequest {
+    pub patient_id: Uuid,
+    pub practitioner_id: Uuid,
+    pub reason: Option<String>,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: Option<DateTime<Utc>>,
+    /// State the patient will be located in during the visit
+    pub patient_state: Option<String>,
+    /// States the practitioner is licensed in
+    #[serde(default)]
+    pub practitioner_licenses: Vec<String>,
+}
+
+impl VisitRequest {
+    /// Check the visit and put it in its stored form
+    pub fn check(&mut self) -> Result<(), String> {
+        if self.scheduled_end.is_some_and(|end| end <= self.scheduled_start) {
+            return Err("A visit must end after it starts".to_string());
+        }
+        self.patient_state = self
+            .patient_state
+            .take()
+            .map(|state| state.trim().to_ascii_uppercase())
+            .filter(|state| !state.is_empty());
+        self.practitioner_licenses = self
+            .practitioner_licenses
+            .iter()
+            .map(|state| state.trim().to_ascii_uppercase())
+            .filter(|state| !state.is_empty())
+            .collect();
+        self.reason = self.reason.take().map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusRequest {
+    pub status: VisitStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordingConsentRequest {
+    pub given: bool,
+    pub consenter: Option<String>,
+    pub method: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordingRequest {
+    pub recording: bool,
+}
+
+/// A video session to issue for a visit
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionRequest {
+    pub role: ParticipantRole,
+    /// Name shown to the other participants
+    pub display_name: Option<String>,
+}
+
+/// A short-lived token for joining a visit's room, bound to its encounter
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoSession {
+    pub id: Uuid,
+    pub visit_id: Uuid,
+    pub encounter_id: Uuid,
+    pub provider: String,
+    pub room: String,
+    pub identity: String,
+    pub role: ParticipantRole,
+    pub token: String,
+    pub join_url: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionEventKind {
+    Joined,
+    Left,
+}
+
+impl SessionEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionEventKind::Joined => "joined",
+            SessionEventKind::Left => "left",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "joined" => Some(SessionEventKind::Joined),
+            "left" => Some(SessionEventKind::Left),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionEventRequest {
+    pub event: SessionEventKind,
+}
+
+/// A participant joining or leaving a visit
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub visit_id: Uuid,
+    pub identity: String,
+    pub role: ParticipantRole,
+    pub event: SessionEventKind,
+    pub occurred_at: DateTime<Utc>,
+    pub recorded_by: Option<Uuid>,
+}
+
+/// Whether a prescription may be written during a visit, and if not why
+#[derive(Debug, Clone, Serialize)]
+pub struct PrescriptionDecision {
+    pub visit_id: Uuid,
+    pub allowed: bool,
+    pub violations: Vec<String>,
+    /// State whose rules were applied
+    pub state: Option<String>,
+}
+
+fn visit_from_row(row: &PgRow, video_base_url: Option<&str>) -> TelemedicineVisit {
+    let video_room: String = row.get("video_room");
+    TelemedicineVisit {
+        id: row.get("id"),
+        encounter_id: row.get("encounter_id"),
+        patient_id: row.get("patient_id"),
+        practitioner_id: row.get("practitioner_id"),
+        reason: row.get("reason"),
+        scheduled_start: row.get("scheduled_start"),
+        scheduled_end: row.get("scheduled_end"),
+        patient_state: row.get("patient_state"),
+        practitioner_licenses: row.get("practitioner_licenses"),
+        status: VisitStatus::from_string(row.get("status")).unwrap_or(VisitStatus::Planned),
+        join_url: video_base_url.map(|url| format!("{}/{}", url, video_room)),
+        video_room,
+        started_at: row.get("started_at"),
+        ended_at: row.get("ended_at"),
+        recording_consent: row
+            .get::<Option<Value>, _>("recording_consent")
+            .and_then(|consent| serde_json::from_value(consent).ok()),
+        recording_started_at: row.get("recording_started_at"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn session_event_from_row(row: &PgRow) -> SessionEvent {
+    SessionEvent {
+        id: row.get("id"),
+        session_id: row.get("session_id"),
+        visit_id: row.get("visit_id"),
+        identity: row.get("identity"),
+        role: ParticipantRole::from_string(row.get("role")).unwrap_or(ParticipantRole::Clinician),
+        event: SessionEventKind::from_string(row.get("event")).unwrap_or(SessionEventKind::Joined),
+        occurred_at: row.get("occurred_at"),
+        recorded_by: row.get("recorded_by"),
+    }
+}
+
+fn permits(response: &AuthorizationResponse) -> bool {
+    match response.decision {
+        AccessDecision::Allow
+        | AccessDecision::AllowWithRestrictions
+        | AccessDecision::EmergencyAccess
+        | AccessDecision::BreakGlassAccess => true,
+        AccessDecision::Deny | AccessDecision::RequireApproval | AccessDecision::RequireMFA => false,
+    }
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+fn validation_error(message: String) -> HimsError {
+    HimsError::ValidationError { message }
+}
+
+/// Telemedicine service: video visits held as virtual encounters under the
+/// telehealth rules of the deployment's state, their recording consent, and
+/// the video sessions participants join them through
+pub struct TelemedicineService {
+    pool: PgPool,
+    role_service: RoleService,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    video: Option<Arc<dyn VideoProvider>>,
+    config: TelemedicineConfig,
+    rules: TelemedicineRules,
+}
+
+impl TelemedicineService {
+    /// Create new telemedicine service
+    pub fn new(pool: PgPool, config: TelemedicineConfig, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        let rules = match &config.state {
+            Some(state) => TelemedicineRules::for_state(state).unwrap_or_else(|e| {
+                tracing::error!("No telemedicine rules for {}, so video visits are refused: {}", state, e);
+                TelemedicineRules::unavailable(state)
+            }),
+            None => TelemedicineRules::unrestricted(),
+        };
+        let video = config.video_provider.as_ref().map(VideoProviderConfig::provider);
+        if video.is_none() {
+            tracing::warn!("No video provider configured; video sessions cannot be issued");
+        }
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            authorization_engine,
+            pool,
+            video,
+            config,
+            rules,
+        }
+    }
+
+    async fn require(&self, actor: Uuid, actions: &[Action]) -> Result<(), HimsError> {
+        let permissions = self.role_service.get_user_permissions(actor).await?;
+        for action in actions {
+            if !permissions.contains(action) {
+                tracing::warn!("User {} lacks {} permission", actor, action);
+                return Err(HimsError::SecurityError {
+                    message: format!("Missing required permission: {}", action),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The telehealth rules visits follow
+    pub async fn rules(&self, actor: Uuid) -> Result<TelemedicineRules, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        Ok(self.rules.clone())
+    }
+
+    /// Schedule a video visit, if the state's rules allow it
+    pub async fn schedule(&self, mut request: VisitRequest, actor: Uuid) -> Result<TelemedicineVisit, HimsError> {
+        request.check().map_err(validation_error)?;
+        self.require(actor, &[Action::Schedule]).await?;
+
+        let found: bool = sqlx::query(PATIENT_EXISTS)
+            .bind(request.patient_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?
+            .get("found");
+        if !found {
+            return Err(validation_error(format!("Patient {} does not exist", request.patient_id)));
+        }
+        let (prior_visit, prior_in_person) = self.prior_encounters(request.patient_id, request.practitioner_id).await?;
+        let context = VisitContext {
+            patient_state: request.patient_state.as_deref(),
+            practitioner_licenses: &request.practitioner_licenses,
+            prior_in_person,
+            prior_visit,
+        };
+        Self::enforce(self.rules.check_visit(&context))?;
+
+        let id = Uuid::new_v4();
+        let encounter_id = Uuid::new_v4();
+        let mut period = json!({ "start": request.scheduled_start });
+        if let Some(end) = request.scheduled_end {
+            period["end"] = json!(end);
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        sqlx::query(INSERT_ENCOUNTER)
+            .bind(encounter_id)
+            .bind(VisitStatus::Planned.as_str())
+            .bind(virtual_class())
+            .bind(telemedicine_service_type())
+            .bind(request.patient_id)
+            .bind(practitioner_participant(request.practitioner_id))
+            .bind(period)
+            .bind(request.reason.as_deref().map(reason_code))
+            .bind(json!({ "versionId": "1", "lastUpdated": Utc::now() }))
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        let row = sqlx::query(INSERT_VISIT)
+            .bind(id)
+            .bind(encounter_id)
+            .bind(request.patient_id)
+            .bind(request.practitioner_id)
+            .bind(&request.reason)
+            .bind(request.scheduled_start)
+            .bind(request.scheduled_end)
+            .bind(&request.patient_state)
+            .bind(&request.practitioner_licenses)
+            .bind(id.simple().to_string())
+            .bind(actor)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        let visit = visit_from_row(&row, self.config.video_base_url.as_deref());
+
+        tracing::info!(
+            "Video visit {} of patient {} with practitioner {} scheduled by {}",
+            visit.id,
+            visit.patient_id,
+            visit.practitioner_id,
+            actor
+        );
+        Ok(visit)
+    }
+
+    /// Whether the practitioner has seen the patient before, and whether
+    /// in person
+    async fn prior_encounters(&self, patient_id: Uuid, practitioner_id: Uuid) -> Result<(bool, bool), HimsError> {
+        let row = sqlx::query(GET_PRIOR_ENCOUNTERS)
+            .bind(patient_id)
+            .bind(practitioner_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok((row.get("prior_visit"), row.get("prior_in_person")))
+    }
+
+    /// Refuse what the rules do not allow, giving every reason
+    fn enforce(violations: Vec<String>) -> Result<(), HimsError> {
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(validation_error(violations.join("; ")))
+    }
+
+    /// Get a video visit
+    pub async fn get(&self, id: Uuid, actor: Uuid) -> Result<Option<TelemedicineVisit>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        self.find_visit(id).await
+    }
+
+    async fn find_visit(&self, id: Uuid) -> Result<Option<TelemedicineVisit>, HimsError> {
+        let row = sqlx::query(GET_VISIT_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.map(|row| visit_from_row(&row, self.config.video_base_url.as_deref())))
+    }
+
+    /// A patient's video visits, latest first
+    pub async fn list_for_patient(&self, patient_id: Uuid, actor: Uuid) -> Result<Vec<TelemedicineVisit>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        let rows = sqlx::query(LIST_PATIENT_VISITS)
+            .bind(patient_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| visit_from_row(row, self.config.video_base_url.as_deref()))
+            .collect())
+    }
+
+    /// Start, finish or cancel a visit, or `None` if there is no such visit.
+    /// A visit starts only if the rules still allow it.
+    pub async fn move_to(
+        &self,
+        id: Uuid,
+        status: VisitStatus,
+        actor: Uuid,
+    ) -> Result<Option<TelemedicineVisit>, HimsError> {
+        self.require(actor, &[Action::Update]).await?;
+        if status == VisitStatus::InProgress {
+            if let Some(visit) = self.find_visit(id).await? {
+                let (prior_visit, prior_in_person) =
+                    self.prior_encounters(visit.patient_id, visit.practitioner_id).await?;
+                Self::enforce(self.rules.check_visit(&VisitContext {
+                    patient_state: visit.patient_state.as_deref(),
+                    practitioner_licenses: &visit.practitioner_licenses,
+                    prior_in_person,
+                    prior_visit,
+                }))?;
+            }
+        }
+
+        let visit = self.progress(id, |visit| visit.move_to(status, Utc::now())).await?;
+        if let Some(visit) = &visit {
+            tracing::info!("Video visit {} moved to {} by {}", id, visit.status.as_str(), actor);
+        }
+        Ok(visit)
+    }
+
+    /// Take the patient's consent or refusal to the visit being recorded,
+    /// or `None` if there is no such visit
+    pub async fn record_consent(
+        &self,
+        id: Uuid,
+        request: RecordingConsentRequest,
+        actor: Uuid,
+    ) -> Result<Option<TelemedicineVisit>, HimsError> {
+        self.require(actor, &[Action::Update]).await?;
+        let consent = RecordingConsent {
+            given: request.given,
+            consenter: request.consenter.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()),
+            method: request.method.map(|m| m.trim().to_string()).filter(|m| !m.is_empty()),
+            recorded_by: actor,
+            recorded_at: Utc::now(),
+        };
+        let given = consent.given;
+
+        let visit = self.progress(id, |visit| visit.record_consent(consent)).await?;
+        if visit.is_some() {
+            let outcome = if given { "given" } else { "refused" };
+            tracing::info!("Recording consent for video visit {} {}, recorded by {}", id, outcome, actor);
+        }
+        Ok(visit)
+    }
+
+    /// Start or stop recording a visit, or `None` if there is no such visit
+    pub async fn set_recording(
+        &self,
+        id: Uuid,
+        recording: bool,
+        actor: Uuid,
+    ) -> Result<Option<TelemedicineVisit>, HimsError> {
+        self.require(actor, &[Action::Update]).await?;
+        let visit = self.progress(id, |visit| visit.set_recording(recording, Utc::now())).await?;
+        if visit.is_some() {
+            let action = if recording { "started" } else { "stopped" };
+            tracing::info!("Recording of video visit {} {} by {}", id, action, actor);
+        }
+        Ok(visit)
+    }
+
+    /// Move a visit on with `step`, keeping its encounter in step, or
+    /// `None` if there is no such visit
+    async fn progress(
+        &self,
+        id: Uuid,
+        step: impl FnOnce(&mut TelemedicineVisit) -> Result<(), String>,
+    ) -> Result<Option<TelemedicineVisit>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut visit) = self.lock_visit(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        let status = visit.status;
+        step(&mut visit).map_err(|message| HimsError::ConflictError { message })?;
+
+        let row = sqlx::query(UPDATE_VISIT)
+            .bind(id)
+            .bind(visit.status.as_str())
+            .bind(visit.started_at)
+            .bind(visit.ended_at)
+            .bind(visit.recording_consent.as_ref().and_then(|consent| serde_json::to_value(consent).ok()))
+            .bind(visit.recording_started_at)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        if visit.status != status {
+            sqlx::query(UPDATE_ENCOUNTER_STATUS)
+                .bind(visit.encounter_id)
+                .bind(visit.status.as_str())
+                .bind(visit.period())
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+        Ok(Some(visit_from_row(&row, self.config.video_base_url.as_deref())))
+    }
+
+    async fn lock_visit(&self, conn: &mut PgConnection, id: Uuid) -> Result<Option<TelemedicineVisit>, HimsError> {
+        let row = sqlx::query(LOCK_VISIT)
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(database_error)?;
+        Ok(row.map(|row| visit_from_row(&row, self.config.video_base_url.as_deref())))
+    }
+
+    /// Check a prescription against the state's rules before it is written
+    /// during a visit, or `None` if there is no such visit
+    pub async fn check_prescription(
+        &self,
+        id: Uuid,
+        prescription: ProposedPrescription,
+        actor: Uuid,
+    ) -> Result<Option<PrescriptionDecision>, HimsError> {
+        if prescription.medication.trim().is_empty() {
+            return Err(validation_error("A prescription needs a medication".to_string()));
+        }
+        self.require(actor, &[Action::Prescribe]).await?;
+        let Some(visit) = self.find_visit(id).await? else {
+            return Ok(None);
+        };
+        if visit.status != VisitStatus::InProgress {
+            return Err(HimsError::ConflictError {
+                message: format!("Visit {} is {}, not in progress", id, visit.status.as_str()),
+            });
+        }
+
+        let (prior_visit, prior_in_person) = self.prior_encounters(visit.patient_id, visit.practitioner_id).await?;
+        let context = VisitContext {
+            patient_state: visit.patient_state.as_deref(),
+            practitioner_licenses: &visit.practitioner_licenses,
+            prior_in_person,
+            prior_visit,
+        };
+        let violations = self.rules.check_prescription(&context, &prescription);
+        if !violations.is_empty() {
+            tracing::warn!(
+                "Prescription of {} in video visit {} refused under the rules of {}: {}",
+                prescription.medication,
+                id,
+                self.rules.state.as_deref().unwrap_or("no state"),
+                violations.join("; ")
+            );
+        }
+
+        Ok(Some(PrescriptionDecision {
+            visit_id: id,
+            allowed: violations.is_empty(),
+            violations,
+            state: self.rules.state.clone(),
+        }))
+    }
+
+    /// Issue a session token for joining a visit's room, to a participant
+    /// the authorization engine lets read the visit's encounter and
+    /// patient. Patient sessions are issued to staff to pass on. `None` if
+    /// there is no such visit.
+    pub async fn issue_session(
+        &self,
+        id: Uuid,
+        request: SessionRequest,
+        session: SessionContext,
+    ) -> Result<Option<VideoSession>, HimsError> {
+        let actor = session.user_id;
+        self.require(actor, &[Action::Read]).await?;
+        let provider = self.video.clone().ok_or_else(|| HimsError::ConfigurationError {
+            message: "No video provider is configured".to_string(),
+        })?;
+        let Some(visit) = self.find_visit(id).await? else {
+            return Ok(None);
+        };
+        if !matches!(visit.status, VisitStatus::Planned | VisitStatus::InProgress) {
+            return Err(HimsError::ConflictError {
+                message: format!("Visit {} is {}", id, visit.status.as_str()),
+            });
+        }
+        self.verify_participant(&visit, session).await?;
+
+        let issued_at = Utc::now();
+        let grant = VideoGrant {
+            room: visit.video_room.clone(),
+            identity: match request.role {
+                ParticipantRole::Clinician => format!("user-{}", actor),
+                ParticipantRole::Patient => format!("patient-{}", visit.patient_id),
+            },
+            display_name: request.display_name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()),
+            role: request.role,
+            issued_at,
+            expires_at: issued_at + Duration::minutes(self.config.session_ttl_minutes),
+        };
+        let token = provider.issue_token(&grant).await?;
+
+        let session_id = Uuid::new_v4();
+        sqlx::query(INSERT_SESSION)
+            .bind(session_id)
+            .bind(visit.id)
+            .bind(visit.encounter_id)
+            .bind(&grant.identity)
+            .bind(grant.role.as_str())
+            .bind(provider.name())
+            .bind(&grant.room)
+            .bind(actor)
+            .bind(grant.issued_at)
+            .bind(grant.expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+        tracing::info!(
+            "Video session {} for {} in visit {} issued to {}",
+            session_id,
+            grant.identity,
+            visit.id,
+            actor
+        );
+        Ok(Some(VideoSession {
+            id: session_id,
+            visit_id: visit.id,
+            encounter_id: visit.encounter_id,
+            provider: provider.name().to_string(),
+            room: grant.room,
+            identity: grant.identity,
+            role: grant.role,
+            token: token.token,
+            join_url: token.join_url,
+            issued_at: grant.issued_at,
+            expires_at: grant.expires_at,
+        }))
+    }
+
+    /// Refuse sessions to users the authorization engine does not let read
+    /// both the visit's encounter and its patient
+    async fn verify_participant(&self, visit: &TelemedicineVisit, session: SessionContext) -> Result<(), HimsError> {
+        let actor = session.user_id;
+        let requests = [Resource::Encounter(visit.encounter_id), Resource::Patient(visit.patient_id)]
+            .into_iter()
+            .map(|resource| AuthorizationRequest {
+                subject: Subject::User(actor),
+                action: Action::Read,
+                resource,
+                context: RequestContext::new().with_purpose_of_use(PurposeOfUse::Treatment),
+                session: session.clone(),
+                request_id: Some(Uuid::new_v4().to_string()),
+                consistency: Consistency::default(),
+            })
+            .collect();
+        let responses = self
+            .authorization_engine
+            .check_batch(requests)
+            .await
+            .map_err(|e| HimsError::InternalError {
+                message: format!("Authorization check failed: {}", e),
+            })?;
+
+        if let Some(denied) = responses.iter().find(|response| !permits(response)) {
+            tracing::warn!("User {} refused a session for video visit {}: {:?}", actor, visit.id, denied.reasons);
+            return Err(HimsError::SecurityError {
+                message: format!("User {} may not take part in visit {}", actor, visit.id),
+            });
+        }
+        Ok(())
+    }
+
+    /// Log a participant joining or leaving a visit through one of its
+    /// sessions, or `None` if the session is not of the visit
+    pub async fn record_session_event(
+        &self,
+        id: Uuid,
+        session_id: Uuid,
+        event: SessionEventKind,
+        actor: Uuid,
+    ) -> Result<Option<SessionEvent>, HimsError> {
+        self.require(actor, &[Action::Update]).await?;
+        let row = sqlx::query(INSERT_SESSION_EVENT)
+            .bind(Uuid::new_v4())
+            .bind(session_id)
+            .bind(id)
+            .bind(event.as_str())
+            .bind(actor)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let event = row.as_ref().map(session_event_from_row);
+
+        if let Some(event) = &event {
+            tracing::info!(
+                "{} {} video visit {} through session {}",
+                event.identity,
+                event.event.as_str(),
+                id,
+                session_id
+            );
+        }
+        Ok(event)
+    }
+
+    /// A visit's join and leave events, in the order they happened
+    pub async fn list_session_events(&self, id: Uuid, actor: Uuid) -> Result<Vec<SessionEvent>, HimsError> {
+        self.require(actor, &[Action::Read]).await?;
+        let rows = sqlx::query(LIST_SESSION_EVENTS)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(session_event_from_row).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visit() -> TelemedicineVisit {
+        let now = Utc::now();
+        TelemedicineVisit {
+            id: Uuid::new_v4(),
+            encounter_id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            practitioner_id: Uuid::new_v4(),
+            reason: Some("Follow-up".to_string()),
+            scheduled_start: now,
+            scheduled_end: None,
+            patient_state: Some("CA".to_string()),
+            practitioner_licenses: vec!["CA".to_string()],
+            status: VisitStatus::Planned,
+            video_room: "room".to_string(),
+            join_url: None,
+            started_at: None,
+            ended_at: None,
+            recording_consent: None,
+            recording_started_at: None,
+            created_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn consent(given: bool) -> RecordingConsent {
+        RecordingConsent {
+            given,
+            consenter: None,
+            method: Some("verbal".to_string()),
+            recorded_by: Uuid::new_v4(),
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn recording_needs_consent_and_stops_when_it_is_withdrawn() {
+        let mut visit = visit();
+        let now = Utc::now();
+        visit.move_to(VisitStatus::InProgress, now).unwrap();
+        assert!(visit.set_recording(true, now).is_err());
+
+        visit.record_consent(consent(true)).unwrap();
+        visit.set_recording(true, now).unwrap();
+        assert_eq!(visit.recording_started_at, Some(now));
+
+        visit.record_consent(consent(false)).unwrap();
+        assert!(visit.recording_started_at.is_none());
+        assert!(visit.set_recording(true, now).is_err());
+
+        visit.move_to(VisitStatus::Finished, now).unwrap();
+        assert!(visit.move_to(VisitStatus::InProgress, now).is_err());
+        assert!(visit.record_consent(consent(true)).is_err());
+    }
+
+    #[test]
+    fn visits_are_virtual_encounters() {
+        let visit = visit();
+        let encounter = visit.to_encounter();
+        assert_eq!(encounter["resourceType"], "Encounter");
+        assert_eq!(encounter["class"]["code"], "VR");
+        assert_eq!(encounter["serviceType"]["coding"][0]["code"], "448337001");
+        let practitioner = format!("Practitioner/{}", visit.practitioner_id);
+        assert_eq!(encounter["participant"][0]["individual"]["reference"], practitioner);
+        assert_eq!(encounter["reasonCode"][0]["text"], "Follow-up");
+    }
+}
@@ -0,0 +1,119 @@
+//! Telemedicine SQL Queries
+//!
+//! This file contains all SQL queries used by the telemedicine service.
+
+/// Record the encounter a video visit is
+pub const INSERT_ENCOUNTER: &str = r#"
+    INSERT INTO encounters (id, status, class, service_type, subject, participant, period, reason_code, meta)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+"#;
+
+/// Move the encounter a video visit is to a status
+pub const UPDATE_ENCOUNTER_STATUS: &str = r#"
+    UPDATE encounters
+    SET status = $2, period = $3
+    WHERE id = $1
+"#;
+
+/// Schedule a video visit
+pub const INSERT_VISIT: &str = r#"
+    INSERT INTO telemedicine_visits (
+        id, encounter_id, patient_id, practitioner_id, reason, scheduled_start, scheduled_end, patient_state,
+        practitioner_licenses, video_room, created_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+    RETURNING id, encounter_id, patient_id, practitioner_id, reason, scheduled_start, scheduled_end, patient_state,
+              practitioner_licenses, status, video_room, started_at, ended_at, recording_consent,
+              recording_started_at, created_by, created_at, updated_at
+"#;
+
+/// Get a video visit by ID
+pub const GET_VISIT_BY_ID: &str = r#"
+    SELECT id, encounter_id, patient_id, practitioner_id, reason, scheduled_start, scheduled_end, patient_state,
+           practitioner_licenses, status, video_room, started_at, ended_at, recording_consent,
+           recording_started_at, created_by, created_at, updated_at
+    FROM telemedicine_visits
+    WHERE id = $1
+"#;
+
+/// Lock a video visit for an update
+pub const LOCK_VISIT: &str = r#"
+    SELECT id, encounter_id, patient_id, practitioner_id, reason, scheduled_start, scheduled_end, patient_state,
+           practitioner_licenses, status, video_room, started_at, ended_at, recording_consent,
+           recording_started_at, created_by, created_at, updated_at
+    FROM telemedicine_visits
+    WHERE id = $1
+    FOR UPDATE
+"#;
+
+/// A patient's video visits, latest first
+pub const LIST_PATIENT_VISITS: &str = r#"
+    SELECT id, encounter_id, patient_id, practitioner_id, reason, scheduled_start, scheduled_end, patient_state,
+           practitioner_licenses, status, video_room, started_at, ended_at, recording_consent,
+           recording_started_at, created_by, created_at, updated_at
+    FROM telemedicine_visits
+    WHERE patient_id = $1
+    ORDER BY scheduled_start DESC
+"#;
+
+/// Save a video visit's progress and recording
+pub const UPDATE_VISIT: &str = r#"
+    UPDATE telemedicine_visits
+    SET status = $2, started_at = $3, ended_at = $4, recording_consent = $5, recording_started_at = $6
+    WHERE id = $1
+    RETURNING id, encounter_id, patient_id, practitioner_id, reason, scheduled_start, scheduled_end, patient_state,
+              practitioner_licenses, status, video_room, started_at, ended_at, recording_consent,
+              recording_started_at, created_by, created_at, updated_at
+"#;
+
+/// Whether a practitioner has seen a patient before, in a finished
+/// encounter of any class, and whether in one that was not virtual
+pub const GET_PRIOR_ENCOUNTERS: &str = r#"
+    SELECT COUNT(*) > 0 AS prior_visit,
+           COUNT(*) FILTER (WHERE class->>'code' IS DISTINCT FROM 'VR') > 0 AS prior_in_person
+    FROM encounters
+    WHERE subject = $1
+      AND status = 'finished'
+      AND participant @> jsonb_build_array(
+          jsonb_build_object('individual', jsonb_build_object('reference', 'Practitioner/' || $2::text))
+      )
+"#;
+
+/// Whether a patient exists
+pub const PATIENT_EXISTS: &str = r#"
+    SELECT EXISTS (SELECT 1 FROM patients WHERE id = $1) AS found
+"#;
+
+/// Record a video session issued for a visit
+pub const INSERT_SESSION: &str = r#"
+    INSERT INTO telemedicine_sessions (
+        id, visit_id, encounter_id, identity, role, provider, room, issued_to, issued_at, expires_at
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+"#;
+
+/// Log a participant joining or leaving a visit through one of its
+/// sessions; no row if the session is not of the visit
+pub const INSERT_SESSION_EVENT: &str = r#"
+    WITH session AS (
+        SELECT id, visit_id, identity, role
+        FROM telemedicine_sessions
+        WHERE id = $2 AND visit_id = $3
+    ), logged AS (
+        INSERT INTO telemedicine_session_events (id, session_id, visit_id, event, recorded_by)
+        SELECT $1, id, visit_id, $4, $5
+        FROM session
+        RETURNING id, session_id, visit_id, event, occurred_at, recorded_by
+    )
+    SELECT logged.id, logged.session_id, logged.visit_id, logged.event, logged.occurred_at, logged.recorded_by,
+           session.identity, session.role
+    FROM logged
+    JOIN session ON session.id = logged.session_id
+"#;
+
+/// A visit's join and leave events, in the order they happened
+pub const LIST_SESSION_EVENTS: &str = r#"
+    SELECT e.id, e.session_id, e.visit_id, e.event, e.occurred_at, e.recorded_by, s.identity, s.role
+    FROM telemedicine_session_events e
+    JOIN telemedicine_sessions s ON s.id = e.session_id
+    WHERE e.visit_id = $1
+    ORDER BY e.occurred_at, e.id
+"#;
@@ -0,0 +1,265 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::core::HimsError;
+
+/// Who a video session is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParticipantRole {
+    /// Staff taking part, who moderate the room
+    Clinician,
+    /// The patient, to whom staff pass the session on
+    Patient,
+}
+
+impl ParticipantRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParticipantRole::Clinician => "clinician",
+            ParticipantRole::Patient => "patient",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "clinician" => Some(ParticipantRole::Clinician),
+            "patient" => Some(ParticipantRole::Patient),
+            _ => None,
+        }
+    }
+}
+
+/// What a session token lets its holder do: join one room, as one
+/// identity, until it expires
+#[derive(Debug, Clone)]
+pub struct VideoGrant {
+    pub room: String,
+    pub identity: String,
+    pub display_name: Option<String>,
+    pub role: ParticipantRole,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A token for joining a video room, and where to join it if the provider
+/// has a web client
+#[derive(Debug, Clone)]
+pub struct VideoToken {
+    pub token: String,
+    pub join_url: Option<String>,
+}
+
+/// A video service that issues room tokens
+#[async_trait]
+pub trait VideoProvider: Send + Sync {
+    /// Name sessions are recorded under
+    fn name(&self) -> &'static str;
+
+    async fn issue_token(&self, grant: &VideoGrant) -> Result<VideoToken, HimsError>;
+}
+
+/// Credentials of the configured video service
+#[derive(Debug, Clone)]
+pub enum VideoProviderConfig {
+    /// Twilio Video, with an API key of the account
+    Twilio {
+        account_sid: String,
+        api_key_sid: String,
+        api_key_secret: String,
+    },
+    /// Jitsi Meet with JWT authentication, under the app ID and secret its
+    /// Prosody token module is configured with
+    Jitsi {
+        domain: String,
+        app_id: String,
+        app_secret: String,
+    },
+}
+
+impl VideoProviderConfig {
+    /// Credentials of `TELEMEDICINE_VIDEO_PROVIDER`: `twilio`, from
+    /// `TWILIO_ACCOUNT_SID`, `TWILIO_API_KEY_SID` and `TWILIO_API_KEY_SECRET`,
+    /// or `jitsi`, from `JITSI_DOMAIN`, `JITSI_APP_ID` and `JITSI_APP_SECRET`
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let provider = var("TELEMEDICINE_VIDEO_PROVIDER")?.to_lowercase();
+        let config = match provider.as_str() {
+            "twilio" => Some(VideoProviderConfig::Twilio {
+                account_sid: var("TWILIO_ACCOUNT_SID")?,
+                api_key_sid: var("TWILIO_API_KEY_SID")?,
+                api_key_secret: var("TWILIO_API_KEY_SECRET")?,
+            }),
+            "jitsi" => Some(VideoProviderConfig::Jitsi {
+                domain: var("JITSI_DOMAIN")?,
+                app_id: var("JITSI_APP_ID")?,
+                app_secret: var("JITSI_APP_SECRET")?,
+            }),
+            _ => None,
+        };
+        if config.is_none() {
+            tracing::warn!("Unknown video provider {}; video sessions cannot be issued", provider);
+        }
+        config
+    }
+
+    pub fn provider(&self) -> Arc<dyn VideoProvider> {
+        match self.clone() {
+            VideoProviderConfig::Twilio { account_sid, api_key_sid, api_key_secret } => {
+                Arc::new(TwilioVideo { account_sid, api_key_sid, api_key_secret })
+            }
+            VideoProviderConfig::Jitsi { domain, app_id, app_secret } => {
+                Arc::new(JitsiMeet { domain, app_id, app_secret })
+            }
+        }
+    }
+}
+
+fn signing_error(provider: &str, e: jsonwebtoken::errors::Error) -> HimsError {
+    HimsError::InternalError {
+        message: format!("Failed to sign {} video token: {}", provider, e),
+    }
+}
+
+/// Twilio Video access tokens: JWTs signed with an API key secret whose
+/// video grant names the room
+pub struct TwilioVideo {
+    account_sid: String,
+    api_key_sid: String,
+    api_key_secret: String,
+}
+
+#[async_trait]
+impl VideoProvider for TwilioVideo {
+    fn name(&self) -> &'static str {
+        "twilio"
+    }
+
+    async fn issue_token(&self, grant: &VideoGrant) -> Result<VideoToken, HimsError> {
+        let mut header = Header::new(Algorithm::HS256);
+        header.cty = Some("twilio-fpa;v=1".to_string());
+        let claims = json!({
+            "jti": format!("{}-{}", self.api_key_sid, grant.issued_at.timestamp()),
+            "iss": self.api_key_sid,
+            "sub": self.account_sid,
+            "iat": grant.issued_at.timestamp(),
+            "exp": grant.expires_at.timestamp(),
+            "grants": {
+                "identity": grant.identity,
+                "video": { "room": grant.room },
+            },
+        });
+        let token = encode(&header, &claims, &EncodingKey::from_secret(self.api_key_secret.as_bytes()))
+            .map_err(|e| signing_error(self.name(), e))?;
+        Ok(VideoToken { token, join_url: None })
+    }
+}
+
+/// Jitsi Meet JWTs, joined through the web client at
+/// `https://<domain>/<room>?jwt=<token>`
+pub struct JitsiMeet {
+    domain: String,
+    app_id: String,
+    app_secret: String,
+}
+
+#[async_trait]
+impl VideoProvider for JitsiMeet {
+    fn name(&self) -> &'static str {
+        "jitsi"
+    }
+
+    async fn issue_token(&self, grant: &VideoGrant) -> Result<VideoToken, HimsError> {
+        let claims = json!({
+            "aud": "jitsi",
+            "iss": self.app_id,
+            "sub": self.domain,
+            "room": grant.room,
+            "nbf": grant.issued_at.timestamp(),
+            "exp": grant.expires_at.timestamp(),
+            "context": {
+                "user": {
+                    "id": grant.identity,
+                    "name": grant.display_name.as_deref().unwrap_or(&grant.identity),
+                    "moderator": grant.role == ParticipantRole::Clinician,
+                },
+            },
+        });
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(self.app_secret.as_bytes()),
+        )
+        .map_err(|e| signing_error(self.name(), e))?;
+        Ok(VideoToken {
+            join_url: Some(format!("https://{}/{}?jwt={}", self.domain, grant.room, token)),
+            token,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+    use serde_json::Value;
+
+    fn grant(role: ParticipantRole) -> VideoGrant {
+        let issued_at = Utc::now();
+        VideoGrant {
+            room: "visit-room".to_string(),
+            identity: "patient-1".to_string(),
+            display_name: None,
+            role,
+            issued_at,
+            expires_at: issued_at + chrono::Duration::minutes(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn twilio_tokens_grant_one_room() {
+        let provider = VideoProviderConfig::Twilio {
+            account_sid: "AC123".to_string(),
+            api_key_sid: "SK456".to_string(),
+            api_key_secret: "secret".to_string(),
+        }
+        .provider();
+        let token = provider.issue_token(&grant(ParticipantRole::Patient)).await.unwrap();
+        assert!(token.join_url.is_none());
+
+        assert_eq!(decode_header(&token.token).unwrap().cty.as_deref(), Some("twilio-fpa;v=1"));
+        let validation = Validation::new(Algorithm::HS256);
+        let claims = decode::<Value>(&token.token, &DecodingKey::from_secret(b"secret"), &validation)
+            .unwrap()
+            .claims;
+        assert_eq!(claims["iss"], "SK456");
+        assert_eq!(claims["sub"], "AC123");
+        assert_eq!(claims["grants"]["identity"], "patient-1");
+        assert_eq!(claims["grants"]["video"]["room"], "visit-room");
+    }
+
+    #[tokio::test]
+    async fn jitsi_clinicians_moderate() {
+        let provider = VideoProviderConfig::Jitsi {
+            domain: "meet.example.org".to_string(),
+            app_id: "hims".to_string(),
+            app_secret: "secret".to_string(),
+        }
+        .provider();
+        let token = provider.issue_token(&grant(ParticipantRole::Clinician)).await.unwrap();
+        let join_url = token.join_url.unwrap();
+        assert!(join_url.starts_with("https://meet.example.org/visit-room?jwt="));
+
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&["jitsi"]);
+        let claims = decode::<Value>(&token.token, &DecodingKey::from_secret(b"secret"), &validation)
+            .unwrap()
+            .claims;
+        assert_eq!(claims["room"], "visit-room");
+        assert_eq!(claims["context"]["user"]["moderator"], true);
+    }
+}
@@ -0,0 +1,58 @@
+//! Tenant Module
+//! 
+//! This module lets one deployment host multiple clinics including:
+//! - Tenant administration with shared or schema-per-tenant isolation
+//! - Row-level-security isolation via per-connection tenant settings
+//! - Tenant resolution from the `X-Tenant-ID` header, domain or JWT `tid` claim
+//! - Rejection of tokens presented outside the tenant they were issued in, or bound to none
+//! - External issuers mapped to tenants by their own `tid`, e.g. Azure AD directory IDs
+
+#[path = "tenant.controller.rs"]
+pub mod tenant_controller;
+#[path = "tenant.service.rs"]
+pub mod tenant_service;
+#[path = "tenant.middleware.rs"]
+pub mod tenant_middleware;
+#[path = "tenant.sql.rs"]
+pub mod tenant_sql;
+
+pub use tenant_controller::TenantController;
+pub use tenant_service::{Tenant, TenantIsolation, TenantService};
+pub use tenant_middleware::TenantMiddleware;
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Tenant Module Configuration
+pub struct TenantModule {
+    pub service: Arc<TenantService>,
+    pub controller: Arc<TenantController>,
+    pub middleware: Arc<TenantMiddleware>,
+}
+
+impl TenantModule {
+    /// Create a new Tenant Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(TenantService::new(db_pool));
+        let controller = Arc::new(TenantController::new(service.clone()));
+        let middleware = Arc::new(TenantMiddleware::new(service.clone()));
+        
+        Self {
+            service,
+            controller,
+            middleware,
+        }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<TenantService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,176 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::tenant::tenant_service::{CreateTenant, Tenant};
+use crate::modules::tenant::TenantService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Tenant controller for hosting multiple clinics in one deployment
+pub struct TenantController {
+    tenant_service: Arc<TenantService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDomainsRequest {
+    pub domains: Vec<String>,
+}
+
+impl TenantController {
+    /// Create new controller with injected service
+    pub fn new(tenant_service: Arc<TenantService>) -> Self {
+        Self { tenant_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_tenant, "Create a tenant (platform admin)")
+            .get("/", Self::list_tenants, "List tenants (platform admin)")
+            .get("/current", Self::current_tenant, "Tenant the request was resolved to")
+            .get("/:id", Self::get_tenant, "Get tenant by ID")
+            .delete("/:id", Self::deactivate_tenant, "Deactivate a tenant (platform admin)")
+            .put("/:id/domains", Self::update_domains, "Replace the domains a tenant is served on (platform admin)")
+            .with_state(self.tenant_service.clone())
+    }
+
+    /// Create a tenant (platform admin)
+    pub async fn create_tenant(
+        State(tenant_service): State<Arc<TenantService>>,
+        headers: HeaderMap,
+        Json(payload): Json<CreateTenant>,
+    ) -> Result<(StatusCode, Json<Tenant>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("User {} creating tenant {}", actor, payload.slug);
+
+        match tenant_service.create_tenant(actor, payload).await {
+            Ok(tenant) => Ok((StatusCode::CREATED, Json(tenant))),
+            Err(e) => Err(Self::error_response("Failed to create tenant", e)),
+        }
+    }
+
+    /// List tenants (platform admin)
+    pub async fn list_tenants(
+        State(tenant_service): State<Arc<TenantService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<Tenant>>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match tenant_service.list_tenants(actor).await {
+            Ok(tenants) => Ok(Json(tenants)),
+            Err(e) => Err(Self::error_response("Failed to list tenants", e)),
+        }
+    }
+
+    /// Tenant the request was resolved to
+    pub async fn current_tenant(
+        tenant: Option<Extension<Tenant>>,
+    ) -> Result<Json<Tenant>, (StatusCode, Json<ErrorResponse>)> {
+        match tenant {
+            Some(Extension(tenant)) => Ok(Json(tenant)),
+            None => Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Tenant not resolved".to_string(),
+                    message: "No tenant was resolved for this request".to_string(),
+                }),
+            )),
+        }
+    }
+
+    /// Get tenant by ID
+    pub async fn get_tenant(
+        State(tenant_service): State<Arc<TenantService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Tenant>, (StatusCode, Json<ErrorResponse>)> {
+        match tenant_service.get_tenant(id).await {
+            Ok(Some(tenant)) => Ok(Json(tenant)),
+            Ok(None) => {
+                tracing::warn!("Tenant not found: {}", id);
+                Err((
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: "Tenant not found".to_string(),
+                        message: format!("Tenant with id {} not found", id),
+                    }),
+                ))
+            }
+            Err(e) => Err(Self::error_response("Failed to retrieve tenant", e)),
+        }
+    }
+
+    /// Replace the domains a tenant is served on (platform admin)
+    pub async fn update_domains(
+        State(tenant_service): State<Arc<TenantService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<UpdateDomainsRequest>,
+    ) -> Result<Json<Tenant>, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match tenant_service.update_domains(actor, id, payload.domains).await {
+            Ok(tenant) => Ok(Json(tenant)),
+            Err(e) => Err(Self::error_response("Failed to update tenant domains", e)),
+        }
+    }
+
+    /// Deactivate a tenant (platform admin)
+    pub async fn deactivate_tenant(
+        State(tenant_service): State<Arc<TenantService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+
+        match tenant_service.deactivate_tenant(actor, id).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT),
+            Err(e) => Err(Self::error_response("Failed to deactivate tenant", e)),
+        }
+    }
+
+    /// Resolve the acting user from request headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Map service errors to HTTP status codes
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
@@ -0,0 +1,158 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::database::tenant::{with_tenant, DEFAULT_TENANT_ID};
+use crate::modules::auth::auth_jwt::token_verifier;
+use crate::modules::tenant::{Tenant, TenantService};
+
+/// Header naming the tenant by ID or slug, for clients not using tenant domains
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+/// Tenant resolution middleware
+pub struct TenantMiddleware {
+    tenant_service: Arc<TenantService>,
+}
+
+impl TenantMiddleware {
+    pub fn new(tenant_service: Arc<TenantService>) -> Self {
+        Self { tenant_service }
+    }
+
+    pub fn service(&self) -> Arc<TenantService> {
+        self.tenant_service.clone()
+    }
+
+    /// Resolve the request's tenant and run the rest of the request as that
+    /// tenant. The tenant comes from the `X-Tenant-ID` header, else the Host
+    /// domain, else the access token's tenant, else the default tenant. A
+    /// token issued in one tenant is never accepted in another, and a token
+    /// bound to no tenant is rejected.
+    pub async fn resolve(
+        State(tenant_service): State<Arc<TenantService>>,
+        headers: HeaderMap,
+        mut request: Request,
+        next: Next,
+    ) -> Result<Response, StatusCode> {
+        let requested = match headers.get(TENANT_HEADER).and_then(|h| h.to_str().ok()) {
+            Some(reference) => match tenant_service.get_tenant_by_reference(reference).await {
+                Ok(Some(tenant)) => Some(tenant),
+                Ok(None) => {
+                    tracing::warn!("Unknown tenant requested: {}", reference);
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+                Err(e) => {
+                    tracing::error!("Tenant resolution error: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            },
+            None => match headers.get(header::HOST).and_then(|h| h.to_str().ok()) {
+                Some(host) => tenant_service.resolve_domain(host).await.map_err(|e| {
+                    tracing::error!("Tenant resolution error: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?,
+                None => None,
+            },
+        };
+
+        let tenant_id = Self::tenant_for(requested.as_ref().map(|tenant| tenant.id), Self::token_tenant(&headers))?;
+
+        let tenant: Tenant = match requested {
+            Some(tenant) => tenant,
+            None => match tenant_service.get_tenant(tenant_id).await {
+                Ok(Some(tenant)) => tenant,
+                Ok(None) => return Err(StatusCode::FORBIDDEN),
+                Err(e) => {
+                    tracing::error!("Tenant resolution error: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            },
+        };
+
+        if !tenant.is_active {
+            tracing::warn!("Request for inactive tenant {}", tenant.slug);
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let context = tenant.context();
+        request.extensions_mut().insert(tenant);
+        Ok(with_tenant(context, next.run(request)).await)
+    }
+
+    /// The tenant a request runs as, from the tenant it asked for and the
+    /// binding of its token. A token issued in one tenant is never accepted
+    /// in another, and a token bound to no tenant is accepted in none.
+    fn tenant_for(requested: Option<Uuid>, token: TokenTenant) -> Result<Uuid, StatusCode> {
+        match (requested, token) {
+            (Some(requested), TokenTenant::Bound(token_tenant)) if requested != token_tenant => {
+                tracing::warn!("Token for tenant {} presented to tenant {}", token_tenant, requested);
+                Err(StatusCode::FORBIDDEN)
+            }
+            (_, TokenTenant::Unbound) => {
+                tracing::warn!("Token bound to no tenant presented");
+                Err(StatusCode::FORBIDDEN)
+            }
+            (Some(requested), _) => Ok(requested),
+            (None, TokenTenant::Bound(token_tenant)) => Ok(token_tenant),
+            (None, TokenTenant::None) => Ok(DEFAULT_TENANT_ID),
+        }
+    }
+
+    /// Tenant binding of a valid bearer token. Invalid tokens are left for
+    /// authentication to reject.
+    fn token_tenant(headers: &HeaderMap) -> TokenTenant {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let Some((verifier, token)) = token_verifier().zip(token) else {
+            return TokenTenant::None;
+        };
+        match verifier.validate_access_token(token) {
+            Ok(claims) => verifier.token_tenant(&claims).map_or(TokenTenant::Unbound, TokenTenant::Bound),
+            Err(_) => TokenTenant::None,
+        }
+    }
+}
+
+/// Tenant binding of a request's bearer token
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenTenant {
+    /// No valid bearer token
+    None,
+    /// A valid token issued for, or mapped to, this tenant
+    Bound(Uuid),
+    /// A valid token that names no HIMS tenant
+    Unbound,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_for() {
+        let tenant = Uuid::new_v4();
+        let other = Uuid::new_v4();
+
+        assert_eq!(TenantMiddleware::tenant_for(Some(tenant), TokenTenant::Bound(tenant)), Ok(tenant));
+        assert_eq!(TenantMiddleware::tenant_for(None, TokenTenant::Bound(tenant)), Ok(tenant));
+        assert_eq!(
+            TenantMiddleware::tenant_for(Some(other), TokenTenant::Bound(tenant)),
+            Err(StatusCode::FORBIDDEN)
+        );
+
+        // A token without a tenant cannot pick one with a header or host
+        assert_eq!(TenantMiddleware::tenant_for(Some(other), TokenTenant::Unbound), Err(StatusCode::FORBIDDEN));
+        assert_eq!(TenantMiddleware::tenant_for(None, TokenTenant::Unbound), Err(StatusCode::FORBIDDEN));
+
+        // Requests without a token are authenticated by the layers inside
+        assert_eq!(TenantMiddleware::tenant_for(Some(other), TokenTenant::None), Ok(other));
+        assert_eq!(TenantMiddleware::tenant_for(None, TokenTenant::None), Ok(DEFAULT_TENANT_ID));
+    }
+}
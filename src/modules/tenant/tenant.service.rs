@@ -0,0 +1,420 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgArguments;
+use sqlx::query::Query;
+use sqlx::{PgPool, Postgres, Row};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{current_tenant_id, with_tenant, TenantContext, DEFAULT_TENANT_ID};
+use crate::modules::authorization::Action;
+use crate::modules::role::RoleService;
+
+// Import SQL queries from separate file
+use crate::modules::tenant::tenant_sql::*;
+
+/// How long resolved tenants are cached before being re-read
+const TENANT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How a tenant's data is separated from other tenants
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TenantIsolation {
+    /// Shared tables filtered by row-level security
+    Shared,
+    /// Dedicated schema, also protected by row-level security
+    Schema,
+}
+
+impl TenantIsolation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TenantIsolation::Shared => "shared",
+            TenantIsolation::Schema => "schema",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "schema" => TenantIsolation::Schema,
+            _ => TenantIsolation::Shared,
+        }
+    }
+}
+
+/// Clinic or organization hosted by the deployment
+#[derive(Debug, Clone, Serialize)]
+pub struct Tenant {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub domains: Vec<String>,
+    pub isolation: TenantIsolation,
+    pub schema_name: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Tenant {
+    /// Context that scopes database work to this tenant
+    pub fn context(&self) -> TenantContext {
+        let schema = match self.isolation {
+            TenantIsolation::Schema => self.schema_name.clone(),
+            TenantIsolation::Shared => None,
+        };
+        TenantContext::new(self.id, schema)
+    }
+}
+
+/// Request to create a tenant
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateTenant {
+    pub slug: String,
+    pub name: String,
+    #[serde(default)]
+    pub domains: Vec<String>,
+    #[serde(default = "default_isolation")]
+    pub isolation: TenantIsolation,
+}
+
+fn default_isolation() -> TenantIsolation {
+    TenantIsolation::Shared
+}
+
+/// Cached tenant lookups, keyed by ID, slug and domain
+#[derive(Default)]
+struct TenantCache {
+    entries: RwLock<HashMap<String, (Option<Tenant>, Instant)>>,
+}
+
+impl TenantCache {
+    fn get(&self, key: &str) -> Option<Option<Tenant>> {
+        let entries = self.entries.read().ok()?;
+        entries
+            .get(key)
+            .filter(|(_, cached_at)| cached_at.elapsed() < TENANT_CACHE_TTL)
+            .map(|(tenant, _)| tenant.clone())
+    }
+
+    fn insert(&self, key: String, tenant: Option<Tenant>) {
+        if let Ok(mut entries) = self.entries.write() {
+            if entries.len() >= 10_000 {
+                entries.clear();
+            }
+            entries.insert(key, (tenant, Instant::now()));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+    }
+}
+
+/// Service for tenant administration and request-to-tenant resolution
+pub struct TenantService {
+    pool: PgPool,
+    role_service: RoleService,
+    cache: TenantCache,
+}
+
+impl TenantService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_service: RoleService::new(pool.clone()),
+            pool,
+            cache: TenantCache::default(),
+        }
+    }
+
+    /// Create a tenant with the built-in roles. Schema tenants get their
+    /// dedicated schema provisioned first.
+    pub async fn create_tenant(&self, actor: Uuid, request: CreateTenant) -> Result<Tenant, HimsError> {
+        self.require_platform_admin(actor).await?;
+        Self::validate_slug(&request.slug)?;
+        let domains = Self::normalize_domains(&request.domains)?;
+
+        let id = Uuid::new_v4();
+        self.check_domain_conflict(id, &domains).await?;
+
+        let schema_name = match request.isolation {
+            TenantIsolation::Schema => Some(format!("tenant_{}", request.slug.replace('-', "_"))),
+            TenantIsolation::Shared => None,
+        };
+        let now = Utc::now();
+
+        sqlx::query(INSERT_TENANT)
+            .bind(id)
+            .bind(&request.slug)
+            .bind(&request.name)
+            .bind(&domains)
+            .bind(request.isolation.as_str())
+            .bind(&schema_name)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        if let Some(schema) = &schema_name {
+            sqlx::query(PROVISION_TENANT_SCHEMA)
+                .bind(schema)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        }
+
+        let tenant = Tenant {
+            id,
+            slug: request.slug,
+            name: request.name,
+            domains,
+            isolation: request.isolation,
+            schema_name,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        };
+
+        // Reading the default tenant's roles needs to cross tenants
+        let seed = TenantContext {
+            bypass: true,
+            ..tenant.context()
+        };
+        let copied = with_tenant(seed, async {
+            sqlx::query(COPY_SYSTEM_ROLES)
+                .bind(id)
+                .bind(DEFAULT_TENANT_ID)
+                .execute(&self.pool)
+                .await
+        })
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        tracing::info!(
+            "Tenant {} created by {} ({} isolation, {} roles)",
+            tenant.slug,
+            actor,
+            tenant.isolation.as_str(),
+            copied.rows_affected()
+        );
+        Ok(tenant)
+    }
+
+    /// List tenants (platform admin)
+    pub async fn list_tenants(&self, actor: Uuid) -> Result<Vec<Tenant>, HimsError> {
+        self.require_platform_admin(actor).await?;
+
+        let rows = sqlx::query(LIST_TENANTS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(rows.iter().map(Self::row_to_tenant).collect())
+    }
+
+    /// Get tenant by ID
+    pub async fn get_tenant(&self, id: Uuid) -> Result<Option<Tenant>, HimsError> {
+        self.cached_lookup(format!("id:{}", id), sqlx::query(GET_TENANT_BY_ID).bind(id)).await
+    }
+
+    /// Get tenant by its ID or slug, as given in the `X-Tenant-ID` header
+    pub async fn get_tenant_by_reference(&self, reference: &str) -> Result<Option<Tenant>, HimsError> {
+        match Uuid::parse_str(reference) {
+            Ok(id) => self.get_tenant(id).await,
+            Err(_) => {
+                self.cached_lookup(format!("slug:{}", reference), sqlx::query(GET_TENANT_BY_SLUG).bind(reference.to_string()))
+                    .await
+            }
+        }
+    }
+
+    /// Get the tenant serving a host name
+    pub async fn resolve_domain(&self, host: &str) -> Result<Option<Tenant>, HimsError> {
+        let domain = Self::normalize_host(host);
+        self.cached_lookup(format!("domain:{}", domain), sqlx::query(GET_TENANT_BY_DOMAIN).bind(domain.clone())).await
+    }
+
+    /// Replace the domains a tenant is served on
+    pub async fn update_domains(&self, actor: Uuid, id: Uuid, domains: Vec<String>) -> Result<Tenant, HimsError> {
+        self.require_platform_admin(actor).await?;
+        let tenant = self.get_tenant(id).await?.ok_or_else(|| Self::tenant_not_found(id))?;
+        let domains = Self::normalize_domains(&domains)?;
+        self.check_domain_conflict(id, &domains).await?;
+
+        sqlx::query(UPDATE_TENANT_DOMAINS)
+            .bind(id)
+            .bind(&domains)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.cache.clear();
+        Ok(Tenant { domains, updated_at: Utc::now(), ..tenant })
+    }
+
+    /// Deactivate a tenant. Its data is retained but requests are refused.
+    pub async fn deactivate_tenant(&self, actor: Uuid, id: Uuid) -> Result<(), HimsError> {
+        self.require_platform_admin(actor).await?;
+        if id == DEFAULT_TENANT_ID {
+            return Err(HimsError::ValidationError {
+                message: "The default tenant cannot be deactivated".to_string(),
+            });
+        }
+
+        let result = sqlx::query(DEACTIVATE_TENANT)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::tenant_not_found(id));
+        }
+
+        self.cache.clear();
+        tracing::info!("Tenant {} deactivated by {}", id, actor);
+        Ok(())
+    }
+
+    /// Tenants are administered from the default (platform) tenant by users
+    /// holding the configure permission
+    async fn require_platform_admin(&self, actor: Uuid) -> Result<(), HimsError> {
+        if current_tenant_id() != DEFAULT_TENANT_ID {
+            return Err(HimsError::SecurityError {
+                message: "Tenants can only be administered from the platform tenant".to_string(),
+            });
+        }
+        if !self.role_service.get_user_permissions(actor).await?.contains(&Action::Configure) {
+            tracing::warn!("User {} lacks {} permission", actor, Action::Configure);
+            return Err(HimsError::SecurityError {
+                message: format!("Missing required permission: {}", Action::Configure),
+            });
+        }
+        Ok(())
+    }
+
+    async fn cached_lookup(
+        &self,
+        cache_key: String,
+        query: Query<'static, Postgres, PgArguments>,
+    ) -> Result<Option<Tenant>, HimsError> {
+        if let Some(tenant) = self.cache.get(&cache_key) {
+            return Ok(tenant);
+        }
+
+        let tenant = query
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .as_ref()
+            .map(Self::row_to_tenant);
+
+        self.cache.insert(cache_key, tenant.clone());
+        Ok(tenant)
+    }
+
+    async fn check_domain_conflict(&self, id: Uuid, domains: &[String]) -> Result<(), HimsError> {
+        if domains.is_empty() {
+            return Ok(());
+        }
+
+        let conflict = sqlx::query(FIND_DOMAIN_CONFLICT)
+            .bind(id)
+            .bind(domains)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        match conflict {
+            Some(row) => Err(HimsError::ValidationError {
+                message: format!("Domain already served by tenant '{}'", row.get::<String, _>("slug")),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_slug(slug: &str) -> Result<(), HimsError> {
+        let valid = (3..=40).contains(&slug.len())
+            && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !slug.starts_with('-')
+            && !slug.ends_with('-');
+        if valid {
+            Ok(())
+        } else {
+            Err(HimsError::ValidationError {
+                message: "Tenant slug must be 3-40 characters of lowercase letters, digits or '-'".to_string(),
+            })
+        }
+    }
+
+    fn normalize_domains(domains: &[String]) -> Result<Vec<String>, HimsError> {
+        let mut normalized = Vec::new();
+        for domain in domains {
+            let domain = Self::normalize_host(domain);
+            let valid = !domain.is_empty()
+                && domain.len() <= 253
+                && domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+            if !valid {
+                return Err(HimsError::ValidationError {
+                    message: format!("Invalid domain: {}", domain),
+                });
+            }
+            if !normalized.contains(&domain) {
+                normalized.push(domain);
+            }
+        }
+        Ok(normalized)
+    }
+
+    /// Lowercase a Host header value and strip any port
+    pub fn normalize_host(host: &str) -> String {
+        let host = host.trim().to_ascii_lowercase();
+        match host.rsplit_once(':') {
+            Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+            _ => host,
+        }
+    }
+
+    fn tenant_not_found(id: Uuid) -> HimsError {
+        HimsError::ValidationError {
+            message: format!("Tenant with id {} not found", id),
+        }
+    }
+
+    fn row_to_tenant(row: &sqlx::postgres::PgRow) -> Tenant {
+        Tenant {
+            id: row.get("id"),
+            slug: row.get("slug"),
+            name: row.get("name"),
+            domains: row.get("domains"),
+            isolation: TenantIsolation::from_string(row.get::<String, _>("isolation").as_str()),
+            schema_name: row.get("schema_name"),
+            is_active: row.get("is_active"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_and_host_normalization() {
+        assert!(TenantService::validate_slug("city-clinic").is_ok());
+        assert!(TenantService::validate_slug("City Clinic").is_err());
+        assert!(TenantService::validate_slug("-clinic").is_err());
+
+        assert_eq!(TenantService::normalize_host("Clinic.Example.org:8443"), "clinic.example.org");
+        assert_eq!(
+            TenantService::normalize_domains(&["a.example.org".to_string(), "A.example.org".to_string()]).unwrap(),
+            vec!["a.example.org".to_string()]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Tenant SQL Queries
+//!
+//! This file contains all SQL queries used by the tenant service
+//! for clean separation of concerns and better maintainability.
+//! The tenants table itself is not subject to row-level security.
+
+/// Insert a new tenant
+pub const INSERT_TENANT: &str = r#"
+    INSERT INTO tenants (id, slug, name, domains, isolation, schema_name, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+"#;
+
+/// Get tenant by ID
+pub const GET_TENANT_BY_ID: &str = r#"
+    SELECT id, slug, name, domains, isolation, schema_name, is_active, created_at, updated_at
+    FROM tenants
+    WHERE id = $1
+"#;
+
+/// Get tenant by slug
+pub const GET_TENANT_BY_SLUG: &str = r#"
+    SELECT id, slug, name, domains, isolation, schema_name, is_active, created_at, updated_at
+    FROM tenants
+    WHERE slug = $1
+"#;
+
+/// Get tenant serving a domain
+pub const GET_TENANT_BY_DOMAIN: &str = r#"
+    SELECT id, slug, name, domains, isolation, schema_name, is_active, created_at, updated_at
+    FROM tenants
+    WHERE $1 = ANY(domains)
+"#;
+
+/// List tenants
+pub const LIST_TENANTS: &str = r#"
+    SELECT id, slug, name, domains, isolation, schema_name, is_active, created_at, updated_at
+    FROM tenants
+    ORDER BY slug
+"#;
+
+/// Replace the domains a tenant is served on
+pub const UPDATE_TENANT_DOMAINS: &str = r#"
+    UPDATE tenants
+    SET domains = $2
+    WHERE id = $1
+"#;
+
+/// Deactivate a tenant
+pub const DEACTIVATE_TENANT: &str = r#"
+    UPDATE tenants
+    SET is_active = false
+    WHERE id = $1 AND is_active = true
+"#;
+
+/// Whether any other tenant already serves one of the domains
+pub const FIND_DOMAIN_CONFLICT: &str = r#"
+    SELECT slug
+    FROM tenants
+    WHERE id <> $1 AND domains && $2
+    LIMIT 1
+"#;
+
+/// Create the dedicated schema for a schema-isolated tenant
+pub const PROVISION_TENANT_SCHEMA: &str = r#"
+    SELECT provision_tenant_schema($1)
+"#;
+
+/// Copy the built-in roles of the default tenant into a new tenant
+pub const COPY_SYSTEM_ROLES: &str = r#"
+    INSERT INTO roles (id, tenant_id, name, description, permissions, is_system, created_at, updated_at)
+    SELECT uuid_generate_v4(), $1, name, description, permissions, true, NOW(), NOW()
+    FROM public.roles
+    WHERE tenant_id = $2 AND is_system = true AND is_active = true
+"#;
@@ -0,0 +1,56 @@
+//! Theatre Module
+//!
+//! This module provides operating theatre management:
+//! - Theatres, their installed equipment and shared portable equipment
+//! - Case scheduling that refuses clashes of theatre, team members and
+//!   equipment, with turnover time between cases
+//! - The WHO surgical safety checklist, captured per case at sign-in,
+//!   time-out and sign-out, gating the start and completion of the case
+//! - Operative notes written as medical records of the case's patient and
+//!   encounter
+
+#[path = "theatre.controller.rs"]
+pub mod theatre_controller;
+#[path = "theatre.service.rs"]
+pub mod theatre_service;
+#[path = "theatre.sql.rs"]
+pub mod theatre_sql;
+#[path = "theatre.checklist.rs"]
+pub mod theatre_checklist;
+#[path = "theatre.schedule.rs"]
+pub mod theatre_schedule;
+
+pub use theatre_controller::TheatreController;
+pub use theatre_service::{SurgicalCase, Theatre, TheatreService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::medical_record::MedicalRecordService;
+use crate::utils::api_router::ApiRouter;
+
+/// Theatre Module Configuration
+pub struct TheatreModule {
+    pub service: Arc<TheatreService>,
+    pub controller: Arc<TheatreController>,
+}
+
+impl TheatreModule {
+    /// Create a new Theatre Module with dependency injection
+    pub fn new(db_pool: PgPool, medical_records: Arc<MedicalRecordService>) -> Self {
+        let service = Arc::new(TheatreService::new(db_pool, medical_records));
+        let controller = Arc::new(TheatreController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<TheatreService> {
+        self.service.clone()
+    }
+}
@@ -0,0 +1,351 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// A pause point of the WHO surgical safety checklist, in the order they
+/// are done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChecklistPhase {
+    /// Before induction of anaesthesia
+    SignIn,
+    /// Before skin incision
+    TimeOut,
+    /// Before the patient leaves the operating room
+    SignOut,
+}
+
+impl ChecklistPhase {
+    pub const ALL: [ChecklistPhase; 3] = [ChecklistPhase::SignIn, ChecklistPhase::TimeOut, ChecklistPhase::SignOut];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecklistPhase::SignIn => "sign-in",
+            ChecklistPhase::TimeOut => "time-out",
+            ChecklistPhase::SignOut => "sign-out",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "sign-in" => Some(ChecklistPhase::SignIn),
+            "time-out" => Some(ChecklistPhase::TimeOut),
+            "sign-out" => Some(ChecklistPhase::SignOut),
+            _ => None,
+        }
+    }
+}
+
+/// An answer to a checklist item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChecklistAnswer {
+    Yes,
+    No,
+    NotApplicable,
+}
+
+/// How a checklist item is answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum ItemKind {
+    /// Confirmed with yes before the phase is complete; not applicable
+    /// where `allows_na`. When `only_if` names a question, the check is
+    /// needed only when that question was answered yes.
+    Check {
+        allows_na: bool,
+        only_if: Option<&'static str>,
+    },
+    /// A risk the team answers yes or no to
+    Question,
+}
+
+/// An item of the checklist
+#[derive(Debug, Clone, Serialize)]
+pub struct ChecklistItem {
+    pub key: &'static str,
+    pub phase: ChecklistPhase,
+    pub prompt: &'static str,
+    pub kind: ItemKind,
+}
+
+const fn check(key: &'static str, phase: ChecklistPhase, prompt: &'static str) -> ChecklistItem {
+    ChecklistItem {
+        key,
+        phase,
+        prompt,
+        kind: ItemKind::Check {
+            allows_na: false,
+            only_if: None,
+        },
+    }
+}
+
+const fn check_or_na(key: &'static str, phase: ChecklistPhase, prompt: &'static str) -> ChecklistItem {
+    ChecklistItem {
+        key,
+        phase,
+        prompt,
+        kind: ItemKind::Check {
+            allows_na: true,
+            only_if: None,
+        },
+    }
+}
+
+const fn check_if(
+    key: &'static str,
+    phase: ChecklistPhase,
+    prompt: &'static str,
+    question: &'static str,
+) -> ChecklistItem {
+    ChecklistItem {
+        key,
+        phase,
+        prompt,
+        kind: ItemKind::Check {
+            allows_na: false,
+            only_if: Some(question),
+        },
+    }
+}
+
+const fn question(key: &'static str, phase: ChecklistPhase, prompt: &'static str) -> ChecklistItem {
+    ChecklistItem {
+        key,
+        phase,
+        prompt,
+        kind: ItemKind::Question,
+    }
+}
+
+use ChecklistPhase::{SignIn, SignOut, TimeOut};
+
+/// The items of the WHO surgical safety checklist (2009 edition)
+pub const WHO_CHECKLIST: &[ChecklistItem] = &[
+    check("identity-confirmed", SignIn, "Patient has confirmed identity, site, procedure and consent"),
+    check_or_na("site-marked", SignIn, "Site marked"),
+    check("anaesthesia-check-complete", SignIn, "Anaesthesia machine and medication check complete"),
+    check("pulse-oximeter-functioning", SignIn, "Pulse oximeter on patient and functioning"),
+    question("known-allergy", SignIn, "Does the patient have a known allergy?"),
+    question("difficult-airway", SignIn, "Difficult airway or aspiration risk?"),
+    check_if(
+        "airway-equipment-available",
+        SignIn,
+        "Equipment and assistance available",
+        "difficult-airway",
+    ),
+    question("blood-loss-risk", SignIn, "Risk of >500 ml blood loss (7 ml/kg in children)?"),
+    check_if(
+        "blood-loss-access-planned",
+        SignIn,
+        "Two IVs or central access and fluids planned",
+        "blood-loss-risk",
+    ),
+    check("team-introduced", TimeOut, "All team members have introduced themselves by name and role"),
+    check(
+        "patient-procedure-site-confirmed",
+        TimeOut,
+        "Patient's name, procedure and where the incision will be made confirmed",
+    ),
+    check_or_na("antibiotic-prophylaxis", TimeOut, "Antibiotic prophylaxis given within the last 60 minutes"),
+    check(
+        "surgeon-critical-events-reviewed",
+        TimeOut,
+        "Surgeon reviewed critical or non-routine steps, case duration and anticipated blood loss",
+    ),
+    check("anaesthesia-concerns-reviewed", TimeOut, "Anaesthesia team reviewed patient-specific concerns"),
+    check(
+        "sterility-confirmed",
+        TimeOut,
+        "Nursing team confirmed sterility, including indicator results, and raised any equipment issues",
+    ),
+    check_or_na("imaging-displayed", TimeOut, "Essential imaging displayed"),
+    check("procedure-recorded", SignOut, "Name of the procedure recorded"),
+    check("counts-complete", SignOut, "Instrument, sponge and needle counts complete"),
+    check_or_na("specimens-labelled", SignOut, "Specimens labelled, including the patient name"),
+    check_or_na("equipment-problems-addressed", SignOut, "Equipment problems to be addressed identified"),
+    check(
+        "recovery-concerns-reviewed",
+        SignOut,
+        "Surgeon, anaesthetist and nurse reviewed key concerns for recovery and management of the patient",
+    ),
+];
+
+/// The items of one phase of the checklist
+pub fn phase_items(phase: ChecklistPhase) -> impl Iterator<Item = &'static ChecklistItem> {
+    WHO_CHECKLIST.iter().filter(move |item| item.phase == phase)
+}
+
+/// Check the answers given for a phase: every question is answered, every
+/// check that applies is confirmed, and nothing is answered that is not an
+/// item of the phase. The error lists everything that is wrong.
+pub fn check_responses(
+    phase: ChecklistPhase,
+    responses: &BTreeMap<String, ChecklistAnswer>,
+) -> Result<(), String> {
+    let mut problems = Vec::new();
+    for key in responses.keys() {
+        if !phase_items(phase).any(|item| item.key == key) {
+            problems.push(format!("{} is not an item of the {}", key, phase.as_str()));
+        }
+    }
+    for item in phase_items(phase) {
+        let answer = responses.get(item.key).copied();
+        match item.kind {
+            ItemKind::Question => {
+                if !matches!(answer, Some(ChecklistAnswer::Yes) | Some(ChecklistAnswer::No)) {
+                    problems.push(format!("{} needs a yes or no answer", item.key));
+                }
+            }
+            ItemKind::Check { allows_na, only_if } => {
+                let applies = only_if.is_none_or(|question| responses.get(question) == Some(&ChecklistAnswer::Yes));
+                match answer {
+                    Some(ChecklistAnswer::Yes) => {}
+                    Some(ChecklistAnswer::NotApplicable) if allows_na || !applies => {}
+                    None if !applies => {}
+                    Some(ChecklistAnswer::NotApplicable) => problems.push(format!("{} is always applicable", item.key)),
+                    _ => problems.push(format!("{} is not confirmed", item.key)),
+                }
+            }
+        }
+    }
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(format!("The {} is incomplete: {}", phase.as_str(), problems.join("; "))),
+    }
+}
+
+/// A completed phase of the checklist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseRecord {
+    pub completed_at: DateTime<Utc>,
+    /// Who led the phase, usually the checklist coordinator
+    pub completed_by: Option<Uuid>,
+    pub responses: BTreeMap<String, ChecklistAnswer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// The checklist of a surgical case, phase by phase as they are completed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SurgicalSafetyChecklist {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sign_in: Option<PhaseRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_out: Option<PhaseRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sign_out: Option<PhaseRecord>,
+}
+
+impl SurgicalSafetyChecklist {
+    pub fn phase(&self, phase: ChecklistPhase) -> Option<&PhaseRecord> {
+        match phase {
+            ChecklistPhase::SignIn => self.sign_in.as_ref(),
+            ChecklistPhase::TimeOut => self.time_out.as_ref(),
+            ChecklistPhase::SignOut => self.sign_out.as_ref(),
+        }
+    }
+
+    pub fn is_complete(&self, phase: ChecklistPhase) -> bool {
+        self.phase(phase).is_some()
+    }
+
+    /// Record a phase once the phases before it are, if its answers pass
+    /// [`check_responses`]. A phase is recorded only once.
+    pub fn record(&mut self, phase: ChecklistPhase, record: PhaseRecord) -> Result<(), String> {
+        if self.is_complete(phase) {
+            return Err(format!("The {} is already recorded", phase.as_str()));
+        }
+        if let Some(missing) = ChecklistPhase::ALL
+            .iter()
+            .find(|earlier| **earlier < phase && !self.is_complete(**earlier))
+        {
+            return Err(format!("The {} comes before the {}", missing.as_str(), phase.as_str()));
+        }
+        check_responses(phase, &record.responses)?;
+        match phase {
+            ChecklistPhase::SignIn => self.sign_in = Some(record),
+            ChecklistPhase::TimeOut => self.time_out = Some(record),
+            ChecklistPhase::SignOut => self.sign_out = Some(record),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every check of a phase confirmed and every question answered `risk`
+    fn answered(phase: ChecklistPhase, risk: ChecklistAnswer) -> BTreeMap<String, ChecklistAnswer> {
+        phase_items(phase)
+            .map(|item| {
+                let answer = match item.kind {
+                    ItemKind::Question => risk,
+                    ItemKind::Check { .. } => ChecklistAnswer::Yes,
+                };
+                (item.key.to_string(), answer)
+            })
+            .collect()
+    }
+
+    fn record(responses: BTreeMap<String, ChecklistAnswer>) -> PhaseRecord {
+        PhaseRecord {
+            completed_at: Utc::now(),
+            completed_by: None,
+            responses,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_check_responses() {
+        let mut responses = answered(SignIn, ChecklistAnswer::Yes);
+        assert!(check_responses(SignIn, &responses).is_ok());
+
+        responses.insert("site-marked".to_string(), ChecklistAnswer::NotApplicable);
+        assert!(check_responses(SignIn, &responses).is_ok(), "site marking may not apply");
+
+        responses.insert("pulse-oximeter-functioning".to_string(), ChecklistAnswer::No);
+        let message = check_responses(SignIn, &responses).unwrap_err();
+        assert!(message.contains("pulse-oximeter-functioning is not confirmed"), "{}", message);
+
+        let mut responses = answered(SignIn, ChecklistAnswer::No);
+        responses.remove("airway-equipment-available");
+        responses.remove("blood-loss-access-planned");
+        assert!(check_responses(SignIn, &responses).is_ok(), "follow-up checks only apply after a yes");
+
+        responses.insert("blood-loss-risk".to_string(), ChecklistAnswer::Yes);
+        assert!(check_responses(SignIn, &responses).is_err());
+
+        responses.remove("known-allergy");
+        responses.insert("counts-complete".to_string(), ChecklistAnswer::Yes);
+        let message = check_responses(SignIn, &responses).unwrap_err();
+        assert!(message.contains("known-allergy needs a yes or no answer"), "{}", message);
+        assert!(message.contains("counts-complete is not an item of the sign-in"), "{}", message);
+    }
+
+    #[test]
+    fn test_record_phases_in_order() {
+        let mut checklist = SurgicalSafetyChecklist::default();
+        assert!(checklist.record(TimeOut, record(answered(TimeOut, ChecklistAnswer::No))).is_err());
+
+        checklist.record(SignIn, record(answered(SignIn, ChecklistAnswer::No))).unwrap();
+        assert!(checklist.record(SignIn, record(answered(SignIn, ChecklistAnswer::No))).is_err());
+        assert!(checklist.record(SignOut, record(answered(SignOut, ChecklistAnswer::No))).is_err());
+
+        checklist.record(TimeOut, record(answered(TimeOut, ChecklistAnswer::No))).unwrap();
+        checklist.record(SignOut, record(answered(SignOut, ChecklistAnswer::No))).unwrap();
+        assert!(ChecklistPhase::ALL.iter().all(|phase| checklist.is_complete(*phase)));
+
+        let stored: SurgicalSafetyChecklist =
+            serde_json::from_value(serde_json::to_value(&checklist).unwrap()).unwrap();
+        assert_eq!(stored, checklist);
+        assert_eq!(
+            serde_json::from_value::<SurgicalSafetyChecklist>(serde_json::json!({})).unwrap(),
+            SurgicalSafetyChecklist::default()
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::theatre::theatre_checklist::{ChecklistItem, ChecklistPhase, WHO_CHECKLIST};
+use crate::modules::theatre::theatre_schedule::PortableEquipment;
+use crate::modules::theatre::theatre_service::{
+    CaseRequest, ChecklistRequest, OperativeNoteRequest, RescheduleRequest, SurgicalCase, Theatre, TheatreRequest,
+};
+use crate::modules::theatre::TheatreService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// Which cases to list: those booked in a period, a day from `from` unless
+/// `to` is given, optionally in one theatre or of one patient
+#[derive(Debug, Default, Deserialize)]
+pub struct CaseQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub theatre_id: Option<Uuid>,
+    pub patient_id: Option<Uuid>,
+}
+
+/// Portable equipment's name and number of units
+#[derive(Debug, Deserialize)]
+pub struct EquipmentRequest {
+    pub name: String,
+    pub units: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Theatre controller for theatres, the theatre schedule, the surgical
+/// safety checklist and operative notes
+pub struct TheatreController {
+    theatre_service: Arc<TheatreService>,
+}
+
+impl TheatreController {
+    /// Create new controller with injected service
+    pub fn new(theatre_service: Arc<TheatreService>) -> Self {
+        Self { theatre_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/", Self::list_theatres, "List operating theatres")
+            .post("/", Self::create_theatre, "Add an operating theatre")
+            .put("/:id", Self::update_theatre, "Update operating theatre")
+            .get("/equipment", Self::list_equipment, "List portable theatre equipment")
+            .put("/equipment/:code", Self::put_equipment, "Add or update portable theatre equipment")
+            .get("/checklist", Self::checklist, "List the items of the WHO surgical safety checklist")
+            .get("/cases", Self::list_cases, "List surgical cases booked in a period")
+            .post("/cases", Self::schedule_case, "Book a surgical case in a theatre")
+            .get("/cases/:id", Self::get_case, "Get surgical case by ID")
+            .put("/cases/:id/schedule", Self::reschedule_case, "Move a scheduled surgical case")
+            .post("/cases/:id/checklist/:phase", Self::record_checklist, "Record a surgical safety checklist phase")
+            .post("/cases/:id/start", Self::start_case, "Start a surgical case")
+            .post("/cases/:id/complete", Self::complete_case, "Complete a surgical case")
+            .post("/cases/:id/cancel", Self::cancel_case, "Cancel a surgical case")
+            .post("/cases/:id/operative-note", Self::write_operative_note, "Write a surgical case's operative note")
+            .with_state(self.theatre_service.clone())
+    }
+
+    /// Theatres by name
+    pub async fn list_theatres(State(service): State<Arc<TheatreService>>) -> Result<Json<Vec<Theatre>>, ErrorReply> {
+        service
+            .list_theatres()
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list theatres", e))
+    }
+
+    /// Add a theatre
+    pub async fn create_theatre(
+        State(service): State<Arc<TheatreService>>,
+        Json(request): Json<TheatreRequest>,
+    ) -> Result<(StatusCode, Json<Theatre>), ErrorReply> {
+        tracing::info!("Adding theatre {}", request.name);
+
+        match service.create_theatre(request).await {
+            Ok(theatre) => Ok((StatusCode::CREATED, Json(theatre))),
+            Err(e) => Err(Self::error_response("Failed to add theatre", e)),
+        }
+    }
+
+    /// Replace a theatre's details
+    pub async fn update_theatre(
+        State(service): State<Arc<TheatreService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<TheatreRequest>,
+    ) -> Result<Json<Theatre>, ErrorReply> {
+        tracing::info!("Updating theatre {}", id);
+
+        match service.update_theatre(id, request).await {
+            Ok(Some(theatre)) => Ok(Json(theatre)),
+            Ok(None) => Err(Self::not_found("Theatre", id)),
+            Err(e) => Err(Self::error_response("Failed to update theatre", e)),
+        }
+    }
+
+    /// Portable equipment by code
+    pub async fn list_equipment(
+        State(service): State<Arc<TheatreService>>,
+    ) -> Result<Json<Vec<PortableEquipment>>, ErrorReply> {
+        service
+            .list_equipment()
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list equipment", e))
+    }
+
+    /// Add portable equipment, or change its name or number of units
+    pub async fn put_equipment(
+        State(service): State<Arc<TheatreService>>,
+        Path(code): Path<String>,
+        Json(request): Json<EquipmentRequest>,
+    ) -> Result<Json<PortableEquipment>, ErrorReply> {
+        tracing::info!("Setting {} units of equipment {}", request.units, code);
+
+        let equipment = PortableEquipment {
+            code,
+            name: request.name,
+            units: request.units,
+        };
+        service
+            .put_equipment(equipment)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to save equipment", e))
+    }
+
+    /// The checklist's items, for forms to be built from
+    pub async fn checklist() -> Json<&'static [ChecklistItem]> {
+        Json(WHO_CHECKLIST)
+    }
+
+    /// Cases booked in a period, by start
+    pub async fn list_cases(
+        State(service): State<Arc<TheatreService>>,
+        Query(query): Query<CaseQuery>,
+    ) -> Result<Json<Vec<SurgicalCase>>, ErrorReply> {
+        let from = query
+            .from
+            .unwrap_or_else(|| Utc::now().date_naive().and_time(chrono::NaiveTime::MIN).and_utc());
+        let to = query.to.unwrap_or(from + Duration::days(1));
+        service
+            .list_cases(from, to, query.theatre_id, query.patient_id)
+            .await
+            .map(Json)
+            .map_err(|e| Self::error_response("Failed to list cases", e))
+    }
+
+    /// Book a case; answers 409 with what clashes when the theatre, a team
+    /// member or equipment is taken
+    pub async fn schedule_case(
+        State(service): State<Arc<TheatreService>>,
+        headers: HeaderMap,
+        Json(request): Json<CaseRequest>,
+    ) -> Result<(StatusCode, Json<SurgicalCase>), ErrorReply> {
+        tracing::info!("Booking case for patient {} in theatre {}", request.patient_id, request.theatre_id);
+
+        match service.schedule_case(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(case) => Ok((StatusCode::CREATED, Json(case))),
+            Err(e) => Err(Self::error_response("Failed to book case", e)),
+        }
+    }
+
+    /// Get surgical case by ID
+    pub async fn get_case(
+        State(service): State<Arc<TheatreService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<SurgicalCase>, ErrorReply> {
+        match service.get_case(id).await {
+            Ok(Some(case)) => Ok(Json(case)),
+            Ok(None) => Err(Self::not_found("Case", id)),
+            Err(e) => Err(Self::error_response("Failed to get case", e)),
+        }
+    }
+
+    /// Move a scheduled case
+    pub async fn reschedule_case(
+        State(service): State<Arc<TheatreService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<RescheduleRequest>,
+    ) -> Result<Json<SurgicalCase>, ErrorReply> {
+        tracing::info!("Moving case {} to theatre {}", id, request.theatre_id);
+
+        match service.reschedule_case(id, request).await {
+            Ok(Some(case)) => Ok(Json(case)),
+            Ok(None) => Err(Self::not_found("Case", id)),
+            Err(e) => Err(Self::error_response("Failed to move case", e)),
+        }
+    }
+
+    /// Record the sign-in, time-out or sign-out of a case
+    pub async fn record_checklist(
+        State(service): State<Arc<TheatreService>>,
+        headers: HeaderMap,
+        Path((id, phase)): Path<(Uuid, String)>,
+        Json(request): Json<ChecklistRequest>,
+    ) -> Result<Json<SurgicalCase>, ErrorReply> {
+        let Some(phase) = ChecklistPhase::from_string(&phase) else {
+            return Err(Self::error_response(
+                "Failed to record checklist",
+                HimsError::ValidationError {
+                    message: format!("{} is not a checklist phase", phase),
+                },
+            ));
+        };
+        tracing::info!("Recording {} of case {}", phase.as_str(), id);
+
+        match service
+            .record_checklist(id, phase, request, extract_user_from_headers(&headers).ok())
+            .await
+        {
+            Ok(Some(case)) => Ok(Json(case)),
+            Ok(None) => Err(Self::not_found("Case", id)),
+            Err(e) => Err(Self::error_response("Failed to record checklist", e)),
+        }
+    }
+
+    /// Start a case once the sign-in and time-out are recorded
+    pub async fn start_case(
+        State(service): State<Arc<TheatreService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<SurgicalCase>, ErrorReply> {
+        tracing::info!("Starting case {}", id);
+
+        match service.start_case(id).await {
+            Ok(Some(case)) => Ok(Json(case)),
+            Ok(None) => Err(Self::not_found("Case", id)),
+            Err(e) => Err(Self::error_response("Failed to start case", e)),
+        }
+    }
+
+    /// Complete a case once the sign-out is recorded
+    pub async fn complete_case(
+        State(service): State<Arc<TheatreService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<SurgicalCase>, ErrorReply> {
+        tracing::info!("Completing case {}", id);
+
+        match service.complete_case(id).await {
+            Ok(Some(case)) => Ok(Json(case)),
+            Ok(None) => Err(Self::not_found("Case", id)),
+            Err(e) => Err(Self::error_response("Failed to complete case", e)),
+        }
+    }
+
+    /// Cancel a case that has not started
+    pub async fn cancel_case(
+        State(service): State<Arc<TheatreService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<CancelRequest>,
+    ) -> Result<Json<SurgicalCase>, ErrorReply> {
+        tracing::info!("Cancelling case {}", id);
+
+        match service.cancel_case(id, request.reason).await {
+            Ok(Some(case)) => Ok(Json(case)),
+            Ok(None) => Err(Self::not_found("Case", id)),
+            Err(e) => Err(Self::error_response("Failed to cancel case", e)),
+        }
+    }
+
+    /// Write a case's operative note as a medical record; answers with the
+    /// case, linked to the record
+    pub async fn write_operative_note(
+        State(service): State<Arc<TheatreService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<OperativeNoteRequest>,
+    ) -> Result<(StatusCode, Json<SurgicalCase>), ErrorReply> {
+        tracing::info!("Writing operative note of case {}", id);
+
+        match service
+            .write_operative_note(id, request, extract_user_from_headers(&headers).ok())
+            .await
+        {
+            Ok(Some(case)) => Ok((StatusCode::CREATED, Json(case))),
+            Ok(None) => Err(Self::not_found("Case", id)),
+            Err(e) => Err(Self::error_response("Failed to write operative note", e)),
+        }
+    }
+
+    fn not_found(kind: &str, id: Uuid) -> ErrorReply {
+        tracing::warn!("{} not found: {}", kind, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", kind),
+                message: format!("{} with id {} not found", kind, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
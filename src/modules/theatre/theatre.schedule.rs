@@ -0,0 +1,221 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Time a theatre needs between cases to be cleaned and set up
+pub const TURNOVER_MINUTES: i64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TeamRole {
+    Surgeon,
+    AssistantSurgeon,
+    Anaesthetist,
+    ScrubNurse,
+    CirculatingNurse,
+}
+
+impl TeamRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TeamRole::Surgeon => "surgeon",
+            TeamRole::AssistantSurgeon => "assistant-surgeon",
+            TeamRole::Anaesthetist => "anaesthetist",
+            TeamRole::ScrubNurse => "scrub-nurse",
+            TeamRole::CirculatingNurse => "circulating-nurse",
+        }
+    }
+}
+
+/// A member of the team operating
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeamMember {
+    pub user_id: Uuid,
+    pub role: TeamRole,
+}
+
+/// When, where and with whom and what a case is booked; what the schedule
+/// constraints are checked against
+#[derive(Debug, Clone, PartialEq)]
+pub struct Booking {
+    pub case_id: Uuid,
+    pub theatre_id: Uuid,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub team: Vec<TeamMember>,
+    /// Codes of the equipment the case needs
+    pub equipment: Vec<String>,
+}
+
+impl Booking {
+    fn overlaps(&self, other: &Booking, gap: Duration) -> bool {
+        self.start < other.end + gap && other.start < self.end + gap
+    }
+}
+
+/// Equipment moved between theatres, and how many units there are
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortableEquipment {
+    pub code: String,
+    pub name: String,
+    pub units: i32,
+}
+
+/// Check a booking is complete in itself: it ends after it starts and has
+/// a surgeon and an anaesthetist, each team member once
+pub fn check_booking(booking: &Booking) -> Result<(), String> {
+    if booking.end <= booking.start {
+        return Err("A case must end after it starts".to_string());
+    }
+    for role in [TeamRole::Surgeon, TeamRole::Anaesthetist] {
+        if !booking.team.iter().any(|member| member.role == role) {
+            return Err(format!("A case needs a {} on its team", role.as_str()));
+        }
+    }
+    for (i, member) in booking.team.iter().enumerate() {
+        if booking.team[..i].iter().any(|other| other.user_id == member.user_id) {
+            return Err(format!("User {} is on the team more than once", member.user_id));
+        }
+    }
+    Ok(())
+}
+
+/// Why a booking cannot be made: its theatre is taken, a team member is
+/// booked elsewhere, or equipment it needs is not free. `fixed_equipment`
+/// gives the equipment installed in each theatre and `others` the cases
+/// already booked around the same time.
+pub fn find_conflicts(
+    booking: &Booking,
+    others: &[Booking],
+    fixed_equipment: impl Fn(Uuid) -> Vec<String>,
+    portable: &[PortableEquipment],
+) -> Vec<String> {
+    let turnover = Duration::minutes(TURNOVER_MINUTES);
+    let others: Vec<&Booking> = others.iter().filter(|other| other.case_id != booking.case_id).collect();
+    let mut conflicts = Vec::new();
+
+    for other in others.iter().filter(|other| other.theatre_id == booking.theatre_id) {
+        if booking.overlaps(other, turnover) {
+            conflicts.push(format!(
+                "The theatre is booked for case {} from {} to {}, with {} minutes to turn over",
+                other.case_id, other.start, other.end, TURNOVER_MINUTES
+            ));
+        }
+    }
+
+    let concurrent: Vec<&&Booking> = others
+        .iter()
+        .filter(|other| booking.overlaps(other, Duration::zero()))
+        .collect();
+    for member in &booking.team {
+        if let Some(other) = concurrent
+            .iter()
+            .find(|other| other.team.iter().any(|booked| booked.user_id == member.user_id))
+        {
+            conflicts.push(format!("User {} is booked for case {}", member.user_id, other.case_id));
+        }
+    }
+
+    let installed = fixed_equipment(booking.theatre_id);
+    for code in &booking.equipment {
+        if installed.contains(code) {
+            continue;
+        }
+        let Some(equipment) = portable.iter().find(|equipment| &equipment.code == code) else {
+            conflicts.push(format!("{} is neither in the theatre nor portable", code));
+            continue;
+        };
+        let in_use = concurrent
+            .iter()
+            .filter(|other| other.equipment.contains(code) && !fixed_equipment(other.theatre_id).contains(code))
+            .count();
+        if in_use as i32 >= equipment.units {
+            conflicts.push(format!(
+                "All {} units of {} are in use at the time",
+                equipment.units, equipment.name
+            ));
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 6, hour, minute, 0).unwrap()
+    }
+
+    fn booking(theatre_id: Uuid, from: (u32, u32), to: (u32, u32), team: &[Uuid], equipment: &[&str]) -> Booking {
+        Booking {
+            case_id: Uuid::new_v4(),
+            theatre_id,
+            start: at(from.0, from.1),
+            end: at(to.0, to.1),
+            team: team
+                .iter()
+                .enumerate()
+                .map(|(i, user_id)| TeamMember {
+                    user_id: *user_id,
+                    role: if i == 0 { TeamRole::Surgeon } else { TeamRole::Anaesthetist },
+                })
+                .collect(),
+            equipment: equipment.iter().map(|code| code.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_booking() {
+        let (surgeon, anaesthetist) = (Uuid::new_v4(), Uuid::new_v4());
+        let theatre = Uuid::new_v4();
+        assert!(check_booking(&booking(theatre, (8, 0), (10, 0), &[surgeon, anaesthetist], &[])).is_ok());
+        assert!(check_booking(&booking(theatre, (10, 0), (8, 0), &[surgeon, anaesthetist], &[])).is_err());
+        assert!(check_booking(&booking(theatre, (8, 0), (10, 0), &[surgeon], &[])).is_err());
+        assert!(check_booking(&booking(theatre, (8, 0), (10, 0), &[surgeon, surgeon], &[])).is_err());
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let (theatre_1, theatre_2) = (Uuid::new_v4(), Uuid::new_v4());
+        let (surgeon_a, surgeon_b, anaesthetist_a, anaesthetist_b) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let fixed = |theatre: Uuid| match theatre == theatre_2 {
+            true => vec!["c-arm".to_string()],
+            false => Vec::new(),
+        };
+        let portable = vec![PortableEquipment {
+            code: "c-arm".to_string(),
+            name: "C-arm".to_string(),
+            units: 1,
+        }];
+        let booked = vec![booking(theatre_1, (8, 0), (10, 0), &[surgeon_a, anaesthetist_a], &["c-arm"])];
+
+        let clear = booking(theatre_1, (10, 15), (11, 0), &[surgeon_a, anaesthetist_a], &["c-arm"]);
+        assert!(find_conflicts(&clear, &booked, fixed, &portable).is_empty(), "after turnover");
+
+        let too_soon = booking(theatre_1, (10, 5), (11, 0), &[surgeon_b, anaesthetist_b], &[]);
+        let conflicts = find_conflicts(&too_soon, &booked, fixed, &portable);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].contains("turn over"));
+
+        let double_booked = booking(theatre_2, (9, 0), (11, 0), &[surgeon_a, anaesthetist_b], &["c-arm"]);
+        let conflicts = find_conflicts(&double_booked, &booked, fixed, &portable);
+        assert_eq!(conflicts.len(), 1, "theatre 2 has its own C-arm: {:?}", conflicts);
+        assert!(conflicts[0].contains(&surgeon_a.to_string()));
+
+        let theatre_3 = Uuid::new_v4();
+        let needs_c_arm = booking(theatre_3, (9, 0), (11, 0), &[surgeon_b, anaesthetist_b], &["c-arm", "laser"]);
+        let conflicts = find_conflicts(&needs_c_arm, &booked, fixed, &portable);
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts[0].contains("All 1 units of C-arm"));
+        assert!(conflicts[1].contains("laser is neither"));
+
+        let rebooked = Booking {
+            start: at(8, 30),
+            ..booked[0].clone()
+        };
+        assert!(find_conflicts(&rebooked, &booked, fixed, &portable).is_empty(), "a case does not clash with itself");
+    }
+}
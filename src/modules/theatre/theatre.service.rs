@@ -0,0 +1,791 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{CodeableConcept, MedicalRecordType, Reference};
+use crate::modules::auth::AuthContext;
+use crate::modules::medical_record::medical_record_controller::MedicalRecordCreateRequest;
+use crate::modules::medical_record::MedicalRecordService;
+use crate::modules::theatre::theatre_checklist::{ChecklistAnswer, ChecklistPhase, PhaseRecord, SurgicalSafetyChecklist};
+use crate::modules::theatre::theatre_schedule::{
+    check_booking, find_conflicts, Booking, PortableEquipment, TeamMember, TURNOVER_MINUTES,
+};
+
+// Import SQL queries from separate file
+use crate::modules::theatre::theatre_sql::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaseStatus {
+    Scheduled,
+    InProgress,
+    Completed,
+    Cancelled,
+}
+
+impl CaseStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaseStatus::Scheduled => "scheduled",
+            CaseStatus::InProgress => "in-progress",
+            CaseStatus::Completed => "completed",
+            CaseStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "scheduled" => Some(CaseStatus::Scheduled),
+            "in-progress" => Some(CaseStatus::InProgress),
+            "completed" => Some(CaseStatus::Completed),
+            "cancelled" => Some(CaseStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// An operating theatre
+#[derive(Debug, Clone, Serialize)]
+pub struct Theatre {
+    pub id: Uuid,
+    pub name: String,
+    /// Codes of the equipment installed in the theatre
+    pub equipment: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn theatre_from_row(row: &PgRow) -> Theatre {
+    Theatre {
+        id: row.get("id"),
+        name: row.get("name"),
+        equipment: row.get("equipment"),
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// A theatre as clients send it to create or replace one
+#[derive(Debug, Clone, Deserialize)]
+pub struct TheatreRequest {
+    pub name: String,
+    #[serde(default)]
+    pub equipment: Vec<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// An operation booked in a theatre
+#[derive(Debug, Clone, Serialize)]
+pub struct SurgicalCase {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub theatre_id: Uuid,
+    pub procedure: CodeableConcept,
+    /// Site and side of the operation, as marked
+    pub site: Option<String>,
+    pub status: CaseStatus,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+    pub team: Vec<TeamMember>,
+    /// Codes of the equipment the case needs
+    pub equipment: Vec<String>,
+    pub checklist: SurgicalSafetyChecklist,
+    pub actual_start: Option<DateTime<Utc>>,
+    pub actual_end: Option<DateTime<Utc>>,
+    pub cancellation_reason: Option<String>,
+    /// The medical record of the operative note
+    pub operative_note_id: Option<Uuid>,
+    pub note: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn case_from_row(row: &PgRow) -> SurgicalCase {
+    SurgicalCase {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        theatre_id: row.get("theatre_id"),
+        procedure: serde_json::from_value(row.get("procedure")).unwrap_or(CodeableConcept {
+            coding: Vec::new(),
+            text: None,
+        }),
+        site: row.get("site"),
+        status: CaseStatus::from_string(row.get("status")).unwrap_or(CaseStatus::Scheduled),
+        scheduled_start: row.get("scheduled_start"),
+        scheduled_end: row.get("scheduled_end"),
+        team: serde_json::from_value(row.get("team")).unwrap_or_default(),
+        equipment: row.get("equipment"),
+        checklist: serde_json::from_value(row.get("checklist")).unwrap_or_default(),
+        actual_start: row.get("actual_start"),
+        actual_end: row.get("actual_end"),
+        cancellation_reason: row.get("cancellation_reason"),
+        operative_note_id: row.get("operative_note_id"),
+        note: row.get("note"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+impl SurgicalCase {
+    fn booking(&self) -> Booking {
+        Booking {
+            case_id: self.id,
+            theatre_id: self.theatre_id,
+            start: self.scheduled_start,
+            end: self.scheduled_end,
+            team: self.team.clone(),
+            equipment: self.equipment.clone(),
+        }
+    }
+
+    /// Start the operation, once the sign in and time out are done
+    pub fn start(&mut self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.status != CaseStatus::Scheduled {
+            return Err(format!("Case {} is {}", self.id, self.status.as_str()));
+        }
+        for phase in [ChecklistPhase::SignIn, ChecklistPhase::TimeOut] {
+            if !self.checklist.is_complete(phase) {
+                return Err(format!("Case {} cannot start before the {}", self.id, phase.as_str()));
+            }
+        }
+        self.status = CaseStatus::InProgress;
+        self.actual_start = Some(now);
+        Ok(())
+    }
+
+    /// Finish the operation, once the sign out is done
+    pub fn complete(&mut self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.status != CaseStatus::InProgress {
+            return Err(format!("Case {} is {}", self.id, self.status.as_str()));
+        }
+        if !self.checklist.is_complete(ChecklistPhase::SignOut) {
+            return Err(format!("Case {} cannot complete before the sign-out", self.id));
+        }
+        self.status = CaseStatus::Completed;
+        self.actual_end = Some(now);
+        Ok(())
+    }
+
+    /// Cancel a case that has not started
+    pub fn cancel(&mut self, reason: String) -> Result<(), String> {
+        if self.status != CaseStatus::Scheduled {
+            return Err(format!("Case {} is {} and cannot be cancelled", self.id, self.status.as_str()));
+        }
+        self.status = CaseStatus::Cancelled;
+        self.cancellation_reason = Some(reason);
+        Ok(())
+    }
+}
+
+/// A case as clients send it to book one
+#[derive(Debug, Clone, Deserialize)]
+pub struct CaseRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub theatre_id: Uuid,
+    pub procedure: CodeableConcept,
+    pub site: Option<String>,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+    pub team: Vec<TeamMember>,
+    #[serde(default)]
+    pub equipment: Vec<String>,
+    pub note: Option<String>,
+}
+
+/// Where, when and with whom and what a scheduled case is moved to
+#[derive(Debug, Clone, Deserialize)]
+pub struct RescheduleRequest {
+    pub theatre_id: Uuid,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+    pub team: Vec<TeamMember>,
+    #[serde(default)]
+    pub equipment: Vec<String>,
+}
+
+/// The answers given at a pause point of the checklist
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChecklistRequest {
+    pub responses: BTreeMap<String, ChecklistAnswer>,
+    pub note: Option<String>,
+}
+
+/// The operative note of a case, written as a medical record of the case's
+/// patient and encounter
+#[derive(Debug, Clone, Deserialize)]
+pub struct OperativeNoteRequest {
+    /// Rendered from `structured_data` when empty and a template is named
+    #[serde(default)]
+    pub content: String,
+    pub author: Reference,
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub structured_data: Option<serde_json::Value>,
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Database error, or a conflict when a unique constraint was violated
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+/// Theatre service for theatres, their schedule, the surgical safety
+/// checklist and operative notes
+pub struct TheatreService {
+    pool: PgPool,
+    medical_records: Arc<MedicalRecordService>,
+}
+
+impl TheatreService {
+    /// Create new theatre service
+    pub fn new(pool: PgPool, medical_records: Arc<MedicalRecordService>) -> Self {
+        Self { pool, medical_records }
+    }
+
+    /// Add a theatre
+    pub async fn create_theatre(&self, request: TheatreRequest) -> Result<Theatre, HimsError> {
+        if request.name.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "A theatre needs a name".to_string(),
+            });
+        }
+        let row = sqlx::query(INSERT_THEATRE)
+            .bind(Uuid::new_v4())
+            .bind(request.name.trim())
+            .bind(&request.equipment)
+            .bind(request.active)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("A theatre named {} exists", request.name.trim())))?;
+        Ok(theatre_from_row(&row))
+    }
+
+    /// Theatres by name
+    pub async fn list_theatres(&self) -> Result<Vec<Theatre>, HimsError> {
+        let rows = sqlx::query(LIST_THEATRES)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(theatre_from_row).collect())
+    }
+
+    /// Replace a theatre's details, or `None` if there is no such theatre.
+    /// Cases already booked keep their slots.
+    pub async fn update_theatre(&self, id: Uuid, request: TheatreRequest) -> Result<Option<Theatre>, HimsError> {
+        if request.name.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "A theatre needs a name".to_string(),
+            });
+        }
+        let row = sqlx::query(UPDATE_THEATRE)
+            .bind(id)
+            .bind(request.name.trim())
+            .bind(&request.equipment)
+            .bind(request.active)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| write_error(e, || format!("A theatre named {} exists", request.name.trim())))?;
+        Ok(row.as_ref().map(theatre_from_row))
+    }
+
+    /// Portable equipment by code
+    pub async fn list_equipment(&self) -> Result<Vec<PortableEquipment>, HimsError> {
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        Self::portable_equipment(&mut conn).await
+    }
+
+    /// Add portable equipment, or change its name or number of units
+    pub async fn put_equipment(&self, equipment: PortableEquipment) -> Result<PortableEquipment, HimsError> {
+        if equipment.code.trim().is_empty() || equipment.name.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Equipment needs a code and a name".to_string(),
+            });
+        }
+        if equipment.units < 0 {
+            return Err(HimsError::ValidationError {
+                message: "Equipment cannot have a negative number of units".to_string(),
+            });
+        }
+        let row = sqlx::query(UPSERT_EQUIPMENT)
+            .bind(equipment.code.trim())
+            .bind(equipment.name.trim())
+            .bind(equipment.units)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(PortableEquipment {
+            code: row.get("code"),
+            name: row.get("name"),
+            units: row.get("units"),
+        })
+    }
+
+    async fn portable_equipment(conn: &mut PgConnection) -> Result<Vec<PortableEquipment>, HimsError> {
+        let rows = sqlx::query(LIST_EQUIPMENT)
+            .fetch_all(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| PortableEquipment {
+                code: row.get("code"),
+                name: row.get("name"),
+                units: row.get("units"),
+            })
+            .collect())
+    }
+
+    /// Check a booking can be made: it is complete, its theatre is active
+    /// and nothing it needs is taken. Called with the schedule locked.
+    async fn check_slot(conn: &mut PgConnection, booking: &Booking) -> Result<(), HimsError> {
+        check_booking(booking).map_err(|message| HimsError::ValidationError { message })?;
+
+        let theatres: Vec<Theatre> = sqlx::query(LIST_THEATRES)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(theatre_from_row)
+            .collect();
+        match theatres.iter().find(|theatre| theatre.id == booking.theatre_id) {
+            Some(theatre) if theatre.active => {}
+            Some(theatre) => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Theatre {} is inactive", theatre.name),
+                })
+            }
+            None => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Theatre {} does not exist", booking.theatre_id),
+                })
+            }
+        }
+
+        let turnover = Duration::minutes(TURNOVER_MINUTES);
+        let others: Vec<Booking> = sqlx::query(GET_OVERLAPPING_CASES)
+            .bind(booking.start - turnover)
+            .bind(booking.end + turnover)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(database_error)?
+            .iter()
+            .map(|row| case_from_row(row).booking())
+            .collect();
+        let portable = Self::portable_equipment(conn).await?;
+        let fixed = |theatre_id: Uuid| {
+            theatres
+                .iter()
+                .find(|theatre| theatre.id == theatre_id)
+                .map(|theatre| theatre.equipment.clone())
+                .unwrap_or_default()
+        };
+
+        let conflicts = find_conflicts(booking, &others, fixed, &portable);
+        match conflicts.is_empty() {
+            true => Ok(()),
+            false => Err(HimsError::ConflictError {
+                message: conflicts.join("; "),
+            }),
+        }
+    }
+
+    /// Check an encounter a case is linked to is of the case's patient
+    async fn check_encounter(conn: &mut PgConnection, encounter_id: Uuid, patient_id: Uuid) -> Result<(), HimsError> {
+        let subject: Option<Uuid> = sqlx::query(GET_ENCOUNTER_SUBJECT)
+            .bind(encounter_id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?
+            .map(|row| row.get("subject"));
+        match subject {
+            Some(subject) if subject == patient_id => Ok(()),
+            Some(_) => Err(HimsError::ValidationError {
+                message: format!("Encounter {} is not of patient {}", encounter_id, patient_id),
+            }),
+            None => Err(HimsError::ValidationError {
+                message: format!("Encounter {} does not exist", encounter_id),
+            }),
+        }
+    }
+
+    /// Book a case in a theatre, if the theatre, team and equipment are free
+    pub async fn schedule_case(
+        &self,
+        request: CaseRequest,
+        created_by: Option<Uuid>,
+    ) -> Result<SurgicalCase, HimsError> {
+        let booking = Booking {
+            case_id: Uuid::new_v4(),
+            theatre_id: request.theatre_id,
+            start: request.scheduled_start,
+            end: request.scheduled_end,
+            team: request.team,
+            equipment: request.equipment,
+        };
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        if let Some(encounter_id) = request.encounter_id {
+            Self::check_encounter(&mut tx, encounter_id, request.patient_id).await?;
+        }
+        sqlx::query(LOCK_SCHEDULE).execute(&mut *tx).await.map_err(database_error)?;
+        Self::check_slot(&mut tx, &booking).await?;
+
+        let row = sqlx::query(INSERT_CASE)
+            .bind(booking.case_id)
+            .bind(request.patient_id)
+            .bind(request.encounter_id)
+            .bind(booking.theatre_id)
+            .bind(serde_json::to_value(&request.procedure).unwrap_or_default())
+            .bind(&request.site)
+            .bind(booking.start)
+            .bind(booking.end)
+            .bind(serde_json::to_value(&booking.team).unwrap_or_default())
+            .bind(&booking.equipment)
+            .bind(&request.note)
+            .bind(created_by)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        Ok(case_from_row(&row))
+    }
+
+    /// Get surgical case by ID
+    pub async fn get_case(&self, id: Uuid) -> Result<Option<SurgicalCase>, HimsError> {
+        let row = sqlx::query(GET_CASE_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(case_from_row))
+    }
+
+    /// Cases booked in `[from, to)`, optionally in one theatre or of one
+    /// patient only, by start
+    pub async fn list_cases(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        theatre_id: Option<Uuid>,
+        patient_id: Option<Uuid>,
+    ) -> Result<Vec<SurgicalCase>, HimsError> {
+        if to <= from {
+            return Err(HimsError::ValidationError {
+                message: "A theatre list's end must be after its start".to_string(),
+            });
+        }
+        let rows = sqlx::query(LIST_CASES)
+            .bind(from)
+            .bind(to)
+            .bind(theatre_id)
+            .bind(patient_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(case_from_row).collect())
+    }
+
+    async fn lock_case(conn: &mut PgConnection, id: Uuid) -> Result<Option<SurgicalCase>, HimsError> {
+        let row = sqlx::query(LOCK_CASE)
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(row.as_ref().map(case_from_row))
+    }
+
+    /// Move a scheduled case to another theatre, time, team or equipment,
+    /// or `None` if there is no such case
+    pub async fn reschedule_case(
+        &self,
+        id: Uuid,
+        request: RescheduleRequest,
+    ) -> Result<Option<SurgicalCase>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        sqlx::query(LOCK_SCHEDULE).execute(&mut *tx).await.map_err(database_error)?;
+        let Some(mut case) = Self::lock_case(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        if case.status != CaseStatus::Scheduled {
+            return Err(HimsError::ConflictError {
+                message: format!("Case {} is {} and cannot be moved", id, case.status.as_str()),
+            });
+        }
+        case.theatre_id = request.theatre_id;
+        case.scheduled_start = request.scheduled_start;
+        case.scheduled_end = request.scheduled_end;
+        case.team = request.team;
+        case.equipment = request.equipment;
+        Self::check_slot(&mut tx, &case.booking()).await?;
+
+        sqlx::query(UPDATE_CASE_BOOKING)
+            .bind(id)
+            .bind(case.theatre_id)
+            .bind(case.scheduled_start)
+            .bind(case.scheduled_end)
+            .bind(serde_json::to_value(&case.team).unwrap_or_default())
+            .bind(&case.equipment)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_case(id).await
+    }
+
+    /// Record a pause point of the surgical safety checklist, or `None` if
+    /// there is no such case
+    pub async fn record_checklist(
+        &self,
+        id: Uuid,
+        phase: ChecklistPhase,
+        request: ChecklistRequest,
+        completed_by: Option<Uuid>,
+    ) -> Result<Option<SurgicalCase>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut case) = Self::lock_case(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        let expected = match phase {
+            ChecklistPhase::SignIn | ChecklistPhase::TimeOut => CaseStatus::Scheduled,
+            ChecklistPhase::SignOut => CaseStatus::InProgress,
+        };
+        if case.status != expected {
+            return Err(HimsError::ConflictError {
+                message: format!("The {} is not done for a case that is {}", phase.as_str(), case.status.as_str()),
+            });
+        }
+        let record = PhaseRecord {
+            completed_at: Utc::now(),
+            completed_by,
+            responses: request.responses,
+            note: request.note,
+        };
+        case.checklist.record(phase, record).map_err(|message| match case.checklist.is_complete(phase) {
+            true => HimsError::ConflictError { message },
+            false => HimsError::ValidationError { message },
+        })?;
+
+        sqlx::query(UPDATE_CASE_CHECKLIST)
+            .bind(id)
+            .bind(serde_json::to_value(&case.checklist).unwrap_or_default())
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_case(id).await
+    }
+
+    /// Move a case on with `step`, or `None` if there is no such case
+    async fn progress(
+        &self,
+        id: Uuid,
+        step: impl FnOnce(&mut SurgicalCase) -> Result<(), String>,
+    ) -> Result<Option<SurgicalCase>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut case) = Self::lock_case(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        step(&mut case).map_err(|message| HimsError::ConflictError { message })?;
+        sqlx::query(UPDATE_CASE_STATUS)
+            .bind(id)
+            .bind(case.status.as_str())
+            .bind(case.actual_start)
+            .bind(case.actual_end)
+            .bind(&case.cancellation_reason)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_case(id).await
+    }
+
+    /// Start a case once the sign in and time out are recorded
+    pub async fn start_case(&self, id: Uuid) -> Result<Option<SurgicalCase>, HimsError> {
+        self.progress(id, |case| case.start(Utc::now())).await
+    }
+
+    /// Complete a case once the sign out is recorded
+    pub async fn complete_case(&self, id: Uuid) -> Result<Option<SurgicalCase>, HimsError> {
+        self.progress(id, |case| case.complete(Utc::now())).await
+    }
+
+    /// Cancel a case that has not started, freeing its slot
+    pub async fn cancel_case(&self, id: Uuid, reason: String) -> Result<Option<SurgicalCase>, HimsError> {
+        if reason.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Give the reason the case is cancelled".to_string(),
+            });
+        }
+        self.progress(id, |case| case.cancel(reason)).await
+    }
+
+    /// Write the operative note of a case that has started, as an operative
+    /// note medical record of its patient and encounter, and link the case
+    /// to it. `None` if there is no such case.
+    pub async fn write_operative_note(
+        &self,
+        id: Uuid,
+        request: OperativeNoteRequest,
+        author_id: Option<Uuid>,
+    ) -> Result<Option<SurgicalCase>, HimsError> {
+        let Some(case) = self.get_case(id).await? else {
+            return Ok(None);
+        };
+        if !matches!(case.status, CaseStatus::InProgress | CaseStatus::Completed) {
+            return Err(HimsError::ConflictError {
+                message: format!("Case {} is {}; its operative note is written once it starts", id, case.status.as_str()),
+            });
+        }
+        if let Some(note_id) = case.operative_note_id {
+            return Err(HimsError::ConflictError {
+                message: format!("Case {} already has operative note {}", id, note_id),
+            });
+        }
+
+        let record = MedicalRecordCreateRequest {
+            patient_id: case.patient_id,
+            encounter_id: case.encounter_id,
+            record_type: MedicalRecordType::OperativeNote,
+            content: request.content,
+            author: request.author,
+            template_id: request.template_id,
+            structured_data: request.structured_data,
+        };
+        let author = author_id.map(AuthContext::new);
+        let record_id = self.medical_records.create_record_from_request(&record, author.as_ref()).await?;
+        let record_id = Uuid::parse_str(&record_id).map_err(|e| HimsError::InternalError {
+            message: format!("Medical record ID {} is not a UUID: {}", record_id, e),
+        })?;
+
+        let rows_affected = sqlx::query(UPDATE_CASE_OPERATIVE_NOTE)
+            .bind(id)
+            .bind(record_id)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Case {} was given an operative note concurrently", id),
+            });
+        }
+        self.get_case(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::theatre::theatre_checklist::{phase_items, ItemKind};
+    use crate::modules::theatre::theatre_schedule::TeamRole;
+
+    fn case() -> SurgicalCase {
+        let now = Utc::now();
+        SurgicalCase {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            encounter_id: None,
+            theatre_id: Uuid::new_v4(),
+            procedure: CodeableConcept {
+                coding: Vec::new(),
+                text: Some("Laparoscopic cholecystectomy".to_string()),
+            },
+            site: None,
+            status: CaseStatus::Scheduled,
+            scheduled_start: now,
+            scheduled_end: now + Duration::hours(2),
+            team: vec![TeamMember {
+                user_id: Uuid::new_v4(),
+                role: TeamRole::Surgeon,
+            }],
+            equipment: Vec::new(),
+            checklist: SurgicalSafetyChecklist::default(),
+            actual_start: None,
+            actual_end: None,
+            cancellation_reason: None,
+            operative_note_id: None,
+            note: None,
+            created_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn complete_phase(case: &mut SurgicalCase, phase: ChecklistPhase) {
+        let responses = phase_items(phase)
+            .map(|item| {
+                let answer = match item.kind {
+                    ItemKind::Question => ChecklistAnswer::No,
+                    ItemKind::Check { .. } => ChecklistAnswer::Yes,
+                };
+                (item.key.to_string(), answer)
+            })
+            .collect();
+        let record = PhaseRecord {
+            completed_at: Utc::now(),
+            completed_by: None,
+            responses,
+            note: None,
+        };
+        case.checklist.record(phase, record).unwrap();
+    }
+
+    #[test]
+    fn test_case_follows_checklist() {
+        let mut case = case();
+        let now = Utc::now();
+        assert!(case.start(now).is_err(), "no sign in yet");
+        complete_phase(&mut case, ChecklistPhase::SignIn);
+        assert!(case.start(now).is_err(), "no time out yet");
+        complete_phase(&mut case, ChecklistPhase::TimeOut);
+        case.start(now).unwrap();
+        assert_eq!(case.status, CaseStatus::InProgress);
+        assert_eq!(case.actual_start, Some(now));
+
+        assert!(case.cancel("Patient unwell".to_string()).is_err(), "already started");
+        assert!(case.complete(now).is_err(), "no sign out yet");
+        complete_phase(&mut case, ChecklistPhase::SignOut);
+        case.complete(now).unwrap();
+        assert_eq!(case.status, CaseStatus::Completed);
+        assert!(case.start(now).is_err());
+    }
+
+    #[test]
+    fn test_statuses() {
+        for status in [
+            CaseStatus::Scheduled,
+            CaseStatus::InProgress,
+            CaseStatus::Completed,
+            CaseStatus::Cancelled,
+        ] {
+            assert_eq!(CaseStatus::from_string(status.as_str()), Some(status));
+        }
+        let mut case = case();
+        case.cancel("Patient unwell".to_string()).unwrap();
+        assert_eq!(case.status, CaseStatus::Cancelled);
+        assert_eq!(ChecklistPhase::from_string("time-out"), Some(ChecklistPhase::TimeOut));
+    }
+}
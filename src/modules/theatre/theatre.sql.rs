@@ -0,0 +1,140 @@
+//! Theatre SQL Queries
+//!
+//! This file contains all SQL queries used by the theatre service.
+
+/// Insert a new theatre
+pub const INSERT_THEATRE: &str = r#"
+    INSERT INTO theatres (id, name, equipment, active)
+    VALUES ($1, $2, $3, $4)
+    RETURNING id, name, equipment, active, created_at, updated_at
+"#;
+
+/// Get theatre by ID
+pub const GET_THEATRE_BY_ID: &str = r#"
+    SELECT id, name, equipment, active, created_at, updated_at
+    FROM theatres
+    WHERE id = $1
+"#;
+
+/// All theatres, by name
+pub const LIST_THEATRES: &str = r#"
+    SELECT id, name, equipment, active, created_at, updated_at
+    FROM theatres
+    ORDER BY name
+"#;
+
+/// Replace a theatre's details
+pub const UPDATE_THEATRE: &str = r#"
+    UPDATE theatres
+    SET name = $2, equipment = $3, active = $4
+    WHERE id = $1
+    RETURNING id, name, equipment, active, created_at, updated_at
+"#;
+
+/// Portable equipment, by code
+pub const LIST_EQUIPMENT: &str = r#"
+    SELECT code, name, units
+    FROM theatre_equipment
+    ORDER BY code
+"#;
+
+/// Add portable equipment or change its name and number of units
+pub const UPSERT_EQUIPMENT: &str = r#"
+    INSERT INTO theatre_equipment (code, name, units)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (tenant_id, code) DO UPDATE SET name = EXCLUDED.name, units = EXCLUDED.units
+    RETURNING code, name, units
+"#;
+
+/// Serialise changes to the theatre schedule of the current tenant, so two
+/// bookings cannot both take the last free slot, person or unit
+pub const LOCK_SCHEDULE: &str = r#"
+    SELECT pg_advisory_xact_lock(hashtext('theatre-schedule:' || current_tenant_id()::text))
+"#;
+
+/// Insert a new surgical case
+pub const INSERT_CASE: &str = r#"
+    INSERT INTO surgical_cases (
+        id, patient_id, encounter_id, theatre_id, procedure, site, status, scheduled_start, scheduled_end,
+        team, equipment, note, created_by
+    ) VALUES ($1, $2, $3, $4, $5, $6, 'scheduled', $7, $8, $9, $10, $11, $12)
+    RETURNING id, patient_id, encounter_id, theatre_id, procedure, site, status, scheduled_start,
+              scheduled_end, team, equipment, checklist, actual_start, actual_end, cancellation_reason,
+              operative_note_id, note, created_by, created_at, updated_at
+"#;
+
+/// Get surgical case by ID
+pub const GET_CASE_BY_ID: &str = r#"
+    SELECT id, patient_id, encounter_id, theatre_id, procedure, site, status, scheduled_start, scheduled_end,
+           team, equipment, checklist, actual_start, actual_end, cancellation_reason, operative_note_id, note,
+           created_by, created_at, updated_at
+    FROM surgical_cases
+    WHERE id = $1
+"#;
+
+/// Lock a surgical case while its checklist or status changes
+pub const LOCK_CASE: &str = r#"
+    SELECT id, patient_id, encounter_id, theatre_id, procedure, site, status, scheduled_start, scheduled_end,
+           team, equipment, checklist, actual_start, actual_end, cancellation_reason, operative_note_id, note,
+           created_by, created_at, updated_at
+    FROM surgical_cases
+    WHERE id = $1
+    FOR UPDATE
+"#;
+
+/// Cases booked in [$1, $2), optionally only in theatre $3 or of patient $4,
+/// by start
+pub const LIST_CASES: &str = r#"
+    SELECT id, patient_id, encounter_id, theatre_id, procedure, site, status, scheduled_start, scheduled_end,
+           team, equipment, checklist, actual_start, actual_end, cancellation_reason, operative_note_id, note,
+           created_by, created_at, updated_at
+    FROM surgical_cases
+    WHERE scheduled_start < $2 AND scheduled_end > $1
+      AND ($3::uuid IS NULL OR theatre_id = $3) AND ($4::uuid IS NULL OR patient_id = $4)
+    ORDER BY scheduled_start, theatre_id
+"#;
+
+/// Scheduled and running cases overlapping [$1, $2), which a booking in
+/// that window could clash with
+pub const GET_OVERLAPPING_CASES: &str = r#"
+    SELECT id, patient_id, encounter_id, theatre_id, procedure, site, status, scheduled_start, scheduled_end,
+           team, equipment, checklist, actual_start, actual_end, cancellation_reason, operative_note_id, note,
+           created_by, created_at, updated_at
+    FROM surgical_cases
+    WHERE status IN ('scheduled', 'in-progress') AND scheduled_start < $2 AND scheduled_end > $1
+"#;
+
+/// Move a scheduled case to another theatre, time, team or equipment
+pub const UPDATE_CASE_BOOKING: &str = r#"
+    UPDATE surgical_cases
+    SET theatre_id = $2, scheduled_start = $3, scheduled_end = $4, team = $5, equipment = $6
+    WHERE id = $1 AND status = 'scheduled'
+"#;
+
+/// Save a case's checklist
+pub const UPDATE_CASE_CHECKLIST: &str = r#"
+    UPDATE surgical_cases
+    SET checklist = $2
+    WHERE id = $1
+"#;
+
+/// Save a case's progress through the day
+pub const UPDATE_CASE_STATUS: &str = r#"
+    UPDATE surgical_cases
+    SET status = $2, actual_start = $3, actual_end = $4, cancellation_reason = $5
+    WHERE id = $1
+"#;
+
+/// Link a case to its operative note, unless it already has one
+pub const UPDATE_CASE_OPERATIVE_NOTE: &str = r#"
+    UPDATE surgical_cases
+    SET operative_note_id = $2
+    WHERE id = $1 AND operative_note_id IS NULL
+"#;
+
+/// Patient an encounter is of
+pub const GET_ENCOUNTER_SUBJECT: &str = r#"
+    SELECT subject
+    FROM encounters
+    WHERE id = $1
+"#;
@@ -0,0 +1,56 @@
+//! Vital Record Module
+//!
+//! This module provides birth and death notification for civil registration:
+//! - Structured birth and death notifications, linked to the mother's or the
+//!   deceased's encounter
+//! - The medical certificate of cause of death, its conditions coded in
+//!   ICD-10 and the underlying cause selected from them
+//! - Finalization, after which a record only changes by amendment, each
+//!   kept with its reason and the particulars it replaced
+//! - Export to civil registration as India's CRS report forms or as CSV
+
+#[path = "vital_record.controller.rs"]
+pub mod vital_record_controller;
+#[path = "vital_record.service.rs"]
+pub mod vital_record_service;
+#[path = "vital_record.sql.rs"]
+pub mod vital_record_sql;
+#[path = "vital_record.cause.rs"]
+pub mod vital_record_cause;
+#[path = "vital_record.export.rs"]
+pub mod vital_record_export;
+
+pub use vital_record_controller::VitalRecordController;
+pub use vital_record_service::{VitalRecord, VitalRecordConfig, VitalRecordService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::patient::PatientService;
+use crate::utils::api_router::ApiRouter;
+
+/// Vital Record Module Configuration
+pub struct VitalRecordModule {
+    pub service: Arc<VitalRecordService>,
+    pub controller: Arc<VitalRecordController>,
+}
+
+impl VitalRecordModule {
+    /// Create a new Vital Record Module with dependency injection
+    pub fn new(db_pool: PgPool, patients: Arc<PatientService>, config: VitalRecordConfig) -> Self {
+        let service = Arc::new(VitalRecordService::new(db_pool, patients, config));
+        let controller = Arc::new(VitalRecordController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<VitalRecordService> {
+        self.service.clone()
+    }
+}
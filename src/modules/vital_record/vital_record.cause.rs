@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+/// Line of the medical certificate of cause of death a condition is entered
+/// on: Part I lines (a) to (d), the immediate cause down to the one that
+/// started the train of events, or Part II, conditions that contributed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CauseLine {
+    A,
+    B,
+    C,
+    D,
+    Contributing,
+}
+
+impl CauseLine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CauseLine::A => "a",
+            CauseLine::B => "b",
+            CauseLine::C => "c",
+            CauseLine::D => "d",
+            CauseLine::Contributing => "contributing",
+        }
+    }
+}
+
+/// A condition on the certificate, as certified and as coded in ICD-10
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CauseOfDeath {
+    pub line: CauseLine,
+    pub description: String,
+    pub icd10: Option<String>,
+    /// Approximate interval between onset and death, e.g. "3 days"
+    pub interval: Option<String>,
+}
+
+/// An ICD-10 code in its dotted form, e.g. `I21.9`: a letter, two digits
+/// and up to two characters of subcategory, with or without the dot
+pub fn normalize_icd10(code: &str) -> Result<String, String> {
+    let compact: String = code.trim().to_ascii_uppercase().chars().filter(|c| *c != '.').collect();
+    let chars: Vec<char> = compact.chars().collect();
+    let valid = (3..=5).contains(&chars.len())
+        && chars[0].is_ascii_uppercase()
+        && chars[1].is_ascii_digit()
+        && chars[2].is_ascii_digit()
+        && chars[3..].iter().all(|c| c.is_ascii_alphanumeric());
+    match (valid, chars.len()) {
+        (false, _) => Err(format!("{:?} is not an ICD-10 code", code)),
+        (true, 3) => Ok(compact),
+        (true, _) => Ok(format!("{}.{}", &compact[..3], &compact[3..])),
+    }
+}
+
+/// Whether a code is an external cause of morbidity and mortality, chapter
+/// XX (V01-Y98)
+pub fn is_external_cause(code: &str) -> bool {
+    matches!(code.chars().next(), Some('V' | 'W' | 'X' | 'Y'))
+}
+
+/// Normalize the codes of a certificate's conditions, and check its Part I
+/// is filled from line (a) down without gaps, one condition a line
+pub fn check_causes(causes: &mut [CauseOfDeath]) -> Result<(), String> {
+    for cause in causes.iter_mut() {
+        if cause.description.trim().is_empty() {
+            return Err(format!("The condition on line {} needs a description", cause.line.as_str()));
+        }
+        if let Some(code) = &cause.icd10 {
+            cause.icd10 = Some(normalize_icd10(code)?);
+        }
+    }
+
+    let part_one = [CauseLine::A, CauseLine::B, CauseLine::C, CauseLine::D];
+    let mut ended = false;
+    for line in part_one {
+        match causes.iter().filter(|cause| cause.line == line).count() {
+            0 => ended = true,
+            1 if ended => return Err(format!("Part I line {} is filled but the line above it is not", line.as_str())),
+            1 => {}
+            _ => return Err(format!("Part I line {} has more than one condition", line.as_str())),
+        }
+    }
+    Ok(())
+}
+
+/// The underlying cause of death, by the general principle: the condition
+/// on the lowest filled line of Part I. When the death was not natural and
+/// the external cause was coded, it is the external cause instead.
+pub fn underlying_cause(causes: &[CauseOfDeath], natural: bool) -> Option<String> {
+    let coded = || causes.iter().filter_map(|cause| cause.icd10.as_deref());
+    if !natural {
+        if let Some(external) = coded().find(|code| is_external_cause(code)) {
+            return Some(external.to_string());
+        }
+    }
+    causes
+        .iter()
+        .filter(|cause| cause.line != CauseLine::Contributing)
+        .max_by_key(|cause| cause.line)
+        .and_then(|cause| cause.icd10.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cause(line: CauseLine, icd10: &str) -> CauseOfDeath {
+        CauseOfDeath {
+            line,
+            description: format!("Condition {}", icd10),
+            icd10: Some(icd10.to_string()),
+            interval: None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_icd10() {
+        assert_eq!(normalize_icd10("i219").unwrap(), "I21.9");
+        assert_eq!(normalize_icd10(" J18.9 ").unwrap(), "J18.9");
+        assert_eq!(normalize_icd10("A09").unwrap(), "A09");
+        assert_eq!(normalize_icd10("U07.1").unwrap(), "U07.1");
+        assert!(normalize_icd10("I2").is_err());
+        assert!(normalize_icd10("12.9").is_err());
+        assert!(normalize_icd10("I21.9XX").is_err());
+    }
+
+    #[test]
+    fn test_check_causes() {
+        let mut causes = vec![
+            cause(CauseLine::A, "i219"),
+            cause(CauseLine::B, "I25.1"),
+            cause(CauseLine::Contributing, "E11.9"),
+        ];
+        assert!(check_causes(&mut causes).is_ok());
+        assert_eq!(causes[0].icd10.as_deref(), Some("I21.9"));
+
+        let mut gap = vec![cause(CauseLine::A, "I21.9"), cause(CauseLine::C, "I10")];
+        assert!(check_causes(&mut gap).is_err());
+        let mut twice = vec![cause(CauseLine::A, "I21.9"), cause(CauseLine::A, "I10")];
+        assert!(check_causes(&mut twice).is_err());
+        let mut blank = vec![CauseOfDeath {
+            description: " ".to_string(),
+            ..cause(CauseLine::A, "I21.9")
+        }];
+        assert!(check_causes(&mut blank).is_err());
+    }
+
+    #[test]
+    fn test_underlying_cause() {
+        let causes = vec![
+            cause(CauseLine::A, "I21.9"),
+            cause(CauseLine::B, "I25.1"),
+            cause(CauseLine::Contributing, "E11.9"),
+        ];
+        assert_eq!(underlying_cause(&causes, true).as_deref(), Some("I25.1"));
+
+        let injury = vec![cause(CauseLine::A, "S06.9"), cause(CauseLine::B, "V43.5")];
+        assert_eq!(underlying_cause(&injury, false).as_deref(), Some("V43.5"));
+        assert_eq!(underlying_cause(&injury[..1], false).as_deref(), Some("S06.9"));
+        assert_eq!(underlying_cause(&[], true), None);
+    }
+}
@@ -0,0 +1,246 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::vital_record::vital_record_export::{ExportFile, ExportFormat};
+use crate::modules::vital_record::vital_record_service::{
+    AmendRequest, VitalRecord, VitalRecordAmendment, VitalRecordKind, VitalRecordQuery, VitalRecordRequest,
+};
+use crate::modules::vital_record::VitalRecordService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+/// The number a civil registry registered an event under
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub registration_number: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+}
+
+/// Finalized records of one kind of events in [from, to) to export
+#[derive(Debug, Deserialize)]
+pub struct PeriodExportQuery {
+    pub kind: VitalRecordKind,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub format: Option<ExportFormat>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Vital record controller for birth and death notifications and their
+/// export to civil registration
+pub struct VitalRecordController {
+    vital_record_service: Arc<VitalRecordService>,
+}
+
+impl VitalRecordController {
+    /// Create new controller with injected service
+    pub fn new(vital_record_service: Arc<VitalRecordService>) -> Self {
+        Self { vital_record_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_record, "Create a draft birth or death notification")
+            .get("/", Self::list_records, "List birth and death notifications")
+            .get("/export", Self::export_period, "Export a period's finalized notifications for civil registration")
+            .get("/:id", Self::get_record, "Get birth or death notification by ID")
+            .put("/:id", Self::update_record, "Update a draft birth or death notification")
+            .post("/:id/finalize", Self::finalize_record, "Finalize a birth or death notification")
+            .post("/:id/amend", Self::amend_record, "Amend a finalized birth or death notification")
+            .get("/:id/amendments", Self::list_amendments, "List amendments of a birth or death notification")
+            .post("/:id/registration", Self::register_record, "Record the civil registration number of a notification")
+            .get("/:id/export", Self::export_record, "Export a finalized notification for civil registration")
+            .with_state(self.vital_record_service.clone())
+    }
+
+    pub async fn create_record(
+        State(service): State<Arc<VitalRecordService>>,
+        headers: HeaderMap,
+        Json(request): Json<VitalRecordRequest>,
+    ) -> Result<(StatusCode, Json<VitalRecord>), ErrorReply> {
+        tracing::info!("Creating {} record for patient {}", request.details.kind().as_str(), request.patient_id);
+
+        match service.create_record(request, extract_user_from_headers(&headers).ok()).await {
+            Ok(record) => Ok((StatusCode::CREATED, Json(record))),
+            Err(e) => Err(Self::error_response("Failed to create vital record", e)),
+        }
+    }
+
+    pub async fn list_records(
+        State(service): State<Arc<VitalRecordService>>,
+        Query(query): Query<VitalRecordQuery>,
+    ) -> Result<Json<Vec<VitalRecord>>, ErrorReply> {
+        match service.list_records(&query).await {
+            Ok(records) => Ok(Json(records)),
+            Err(e) => Err(Self::error_response("Failed to list vital records", e)),
+        }
+    }
+
+    pub async fn get_record(
+        State(service): State<Arc<VitalRecordService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<VitalRecord>, ErrorReply> {
+        match service.get_record(id).await {
+            Ok(Some(record)) => Ok(Json(record)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to get vital record", e)),
+        }
+    }
+
+    /// Replace a draft's particulars; answers 409 once it is finalized
+    pub async fn update_record(
+        State(service): State<Arc<VitalRecordService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<VitalRecordRequest>,
+    ) -> Result<Json<VitalRecord>, ErrorReply> {
+        tracing::info!("Updating vital record {}", id);
+
+        match service.update_record(id, request).await {
+            Ok(Some(record)) => Ok(Json(record)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to update vital record", e)),
+        }
+    }
+
+    pub async fn finalize_record(
+        State(service): State<Arc<VitalRecordService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<VitalRecord>, ErrorReply> {
+        tracing::info!("Finalizing vital record {}", id);
+
+        match service.finalize_record(id, extract_user_from_headers(&headers).ok()).await {
+            Ok(Some(record)) => Ok(Json(record)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to finalize vital record", e)),
+        }
+    }
+
+    pub async fn amend_record(
+        State(service): State<Arc<VitalRecordService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<AmendRequest>,
+    ) -> Result<Json<VitalRecord>, ErrorReply> {
+        tracing::info!("Amending vital record {}", id);
+
+        match service.amend_record(id, request, extract_user_from_headers(&headers).ok()).await {
+            Ok(Some(record)) => Ok(Json(record)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to amend vital record", e)),
+        }
+    }
+
+    pub async fn list_amendments(
+        State(service): State<Arc<VitalRecordService>>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<Vec<VitalRecordAmendment>>, ErrorReply> {
+        match service.get_record(id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(Self::not_found(id)),
+            Err(e) => return Err(Self::error_response("Failed to get vital record", e)),
+        }
+        match service.list_amendments(id).await {
+            Ok(amendments) => Ok(Json(amendments)),
+            Err(e) => Err(Self::error_response("Failed to list amendments", e)),
+        }
+    }
+
+    pub async fn register_record(
+        State(service): State<Arc<VitalRecordService>>,
+        Path(id): Path<Uuid>,
+        Json(request): Json<RegisterRequest>,
+    ) -> Result<Json<VitalRecord>, ErrorReply> {
+        tracing::info!("Registering vital record {} as {}", id, request.registration_number);
+
+        match service.register_record(id, request.registration_number).await {
+            Ok(Some(record)) => Ok(Json(record)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to register vital record", e)),
+        }
+    }
+
+    /// Export a finalized record, as its CRS report form by default
+    pub async fn export_record(
+        State(service): State<Arc<VitalRecordService>>,
+        Path(id): Path<Uuid>,
+        Query(query): Query<ExportQuery>,
+    ) -> Result<(HeaderMap, String), ErrorReply> {
+        match service.export_record(id, query.format.unwrap_or(ExportFormat::Crs)).await {
+            Ok(Some(file)) => Ok(Self::file_response(file)),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response("Failed to export vital record", e)),
+        }
+    }
+
+    /// Export the finalized records of a period, as CRS report forms by
+    /// default
+    pub async fn export_period(
+        State(service): State<Arc<VitalRecordService>>,
+        Query(query): Query<PeriodExportQuery>,
+    ) -> Result<(HeaderMap, String), ErrorReply> {
+        tracing::info!("Exporting {} records from {} to {}", query.kind.as_str(), query.from, query.to);
+
+        match service
+            .export_period(query.kind, query.from, query.to, query.format.unwrap_or(ExportFormat::Crs))
+            .await
+        {
+            Ok(file) => Ok(Self::file_response(file)),
+            Err(e) => Err(Self::error_response("Failed to export vital records", e)),
+        }
+    }
+
+    fn file_response(file: ExportFile) -> (HeaderMap, String) {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(file.content_type));
+        (headers, file.body)
+    }
+
+    fn not_found(id: Uuid) -> ErrorReply {
+        tracing::warn!("Vital record not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Vital record not found".to_string(),
+                message: format!("Vital record with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            HimsError::ConflictError { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
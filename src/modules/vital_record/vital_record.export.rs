@@ -0,0 +1,619 @@
+use chrono::{Datelike, FixedOffset, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::models::{Gender, Patient};
+use crate::modules::vital_record::vital_record_cause::{CauseLine, CauseOfDeath};
+use crate::modules::vital_record::vital_record_service::{
+    BirthAttendant, BirthDetails, DeathDetails, DeliveryMethod, MannerOfDeath, MaternalStatus, MedicalAttention,
+    Place, PlaceType, VitalRecord, VitalRecordDetails, VitalRecordKind,
+};
+
+/// Format records are exported to civil registration in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// India's Civil Registration System report forms: Form 1 for births,
+    /// Form 2 for deaths, with Form 4 or 4A where the cause was certified
+    Crs,
+    /// One row a record, for registries taking bulk uploads
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "crs" => Some(ExportFormat::Crs),
+            "csv" => Some(ExportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// An export, ready to be sent
+#[derive(Debug, Clone)]
+pub struct ExportFile {
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+/// A patient's name as registered: its text, or given names and family name
+pub(crate) fn display_name(patient: &Patient) -> Option<String> {
+    let name = patient.name.first()?;
+    if let Some(text) = name.text.clone().filter(|text| !text.trim().is_empty()) {
+        return Some(text);
+    }
+    let parts: Vec<String> = name.given.iter().cloned().chain(name.family.clone()).collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// A patient's first address on one line
+pub(crate) fn address(patient: &Patient) -> Option<String> {
+    let address = patient.address.first()?;
+    if let Some(text) = address.text.clone().filter(|text| !text.trim().is_empty()) {
+        return Some(text);
+    }
+    let parts: Vec<String> = address
+        .line
+        .iter()
+        .cloned()
+        .chain(address.city.clone())
+        .chain(address.district.clone())
+        .chain(address.state.clone())
+        .chain(address.postal_code.clone())
+        .filter(|part| !part.trim().is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+pub(crate) fn sex(gender: &Gender) -> &'static str {
+    match gender {
+        Gender::Male => "Male",
+        Gender::Female => "Female",
+        Gender::Other => "Other",
+        Gender::Unknown => "Unknown",
+    }
+}
+
+/// Age on a date in the largest whole unit: years, else months, else days
+pub(crate) fn age(born: NaiveDate, on: NaiveDate) -> Option<String> {
+    let years = on.years_since(born)?;
+    if years > 0 {
+        return Some(format!("{} years", years));
+    }
+    let months = (on.year() - born.year()) * 12 + on.month() as i32 - born.month() as i32
+        - i32::from(on.day() < born.day());
+    match months > 0 {
+        true => Some(format!("{} months", months)),
+        false => Some(format!("{} days", (on - born).num_days())),
+    }
+}
+
+fn place_type(place: &Place) -> &'static str {
+    match place.place_type {
+        PlaceType::Institution => "Hospital/Institution",
+        PlaceType::Home => "House",
+        PlaceType::Other => "Other place",
+    }
+}
+
+/// A place as the forms name it: its type, then the institution and address
+fn place(place: &Place) -> String {
+    [Some(place_type(place).to_string()), place.name.clone(), place.address.clone()]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn attendant(attendant: BirthAttendant) -> &'static str {
+    match attendant {
+        BirthAttendant::InstitutionalGovernment => "Institutional - Government",
+        BirthAttendant::InstitutionalPrivate => "Institutional - Private",
+        BirthAttendant::DoctorNurseMidwife => "Doctor, Nurse or Trained midwife",
+        BirthAttendant::TraditionalBirthAttendant => "Traditional Birth Attendant",
+        BirthAttendant::RelativesOrOthers => "Relatives or others",
+    }
+}
+
+fn delivery_method(method: DeliveryMethod) -> &'static str {
+    match method {
+        DeliveryMethod::Natural => "Natural",
+        DeliveryMethod::Caesarean => "Caesarean",
+        DeliveryMethod::ForcepsVacuum => "Forceps/Vacuum",
+    }
+}
+
+fn manner(manner: MannerOfDeath) -> &'static str {
+    match manner {
+        MannerOfDeath::Natural => "Natural",
+        MannerOfDeath::Accident => "Accident",
+        MannerOfDeath::Suicide => "Suicide",
+        MannerOfDeath::Homicide => "Homicide",
+        MannerOfDeath::PendingInvestigation => "Pending investigation",
+        MannerOfDeath::Undetermined => "Could not be determined",
+    }
+}
+
+fn medical_attention(attention: MedicalAttention) -> &'static str {
+    match attention {
+        MedicalAttention::Institutional => "Institutional",
+        MedicalAttention::NonInstitutional => "Medical attention other than institutional",
+        MedicalAttention::None => "No medical attention",
+    }
+}
+
+fn maternal_status(status: MaternalStatus) -> &'static str {
+    match status {
+        MaternalStatus::NotPregnant => "No",
+        MaternalStatus::DuringPregnancy => "During pregnancy",
+        MaternalStatus::DuringDelivery => "At the time of delivery",
+        MaternalStatus::WithinSixWeeks => "Within 6 weeks after the end of pregnancy",
+    }
+}
+
+/// A condition as certified, followed by its code
+fn cause(cause: &CauseOfDeath) -> String {
+    match &cause.icd10 {
+        Some(code) => format!("{} ({})", cause.description, code),
+        None => cause.description.clone(),
+    }
+}
+
+fn item(number: &str, label: &str, value: Option<String>) -> Value {
+    json!({ "item": number, "label": label, "value": value })
+}
+
+/// Form 1, the Birth Report
+fn crs_birth(record: &VitalRecord, birth: &BirthDetails, mother: &Patient, offset: FixedOffset) -> Value {
+    let born = record.occurred_at.with_timezone(&offset);
+    let informant = [Some(birth.informant.name.clone()), birth.informant.address.clone()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    json!({
+        "form": "Form 1",
+        "title": "Birth Report",
+        "record_id": record.id,
+        "registration_number": record.registration_number,
+        "legal": [
+            item("1", "Date of birth", Some(born.format("%d/%m/%Y %H:%M").to_string())),
+            item("2", "Sex", Some(sex(&birth.sex).to_string())),
+            item("3", "Name of the child", birth.child_name.clone()),
+            item("4", "Name of the father", birth.father_name.clone()),
+            item("5", "Name of the mother", birth.mother_name.clone().or_else(|| display_name(mother))),
+            item("6", "Place of birth", Some(place(&birth.place))),
+            item("7", "Name and address of the informant", Some(informant)),
+            item("8", "Address of the parents at the time of birth", address(mother)),
+        ],
+        "statistical": [
+            item("10", "Town or village of residence of the mother", birth.residence.clone()),
+            item("11", "Religion of the family", birth.religion.clone()),
+            item("12", "Father's level of education", birth.father_education.clone()),
+            item("13", "Mother's level of education", birth.mother_education.clone()),
+            item("14", "Father's occupation", birth.father_occupation.clone()),
+            item("15", "Mother's occupation", birth.mother_occupation.clone()),
+            item("16", "Age of the mother at the time of marriage", birth.mother_age_at_marriage.map(|age| age.to_string())),
+            item("17", "Age of the mother at the time of this birth", birth.mother_age_years.map(|age| age.to_string())),
+            item(
+                "18",
+                "Number of children born alive to the mother so far including this child",
+                birth.children_born_alive.map(|n| n.to_string()),
+            ),
+            item("19", "Type of attention at delivery", Some(attendant(birth.attendant).to_string())),
+            item("20", "Method of delivery", Some(delivery_method(birth.delivery_method).to_string())),
+            item("21", "Birth weight (in kgs)", birth.birth_weight_grams.map(|grams| format!("{:.2}", grams as f64 / 1000.0))),
+            item("22", "Duration of pregnancy (in weeks)", birth.gestation_weeks.map(|weeks| weeks.to_string())),
+        ],
+    })
+}
+
+/// Form 4 for deaths in institutions or 4A for others, the Medical
+/// Certificate of Cause of Death
+fn crs_cause_certificate(record: &VitalRecord, death: &DeathDetails) -> Value {
+    let line = |line: CauseLine| death.causes.iter().find(|cause| cause.line == line);
+    let part_one: Vec<Value> = [CauseLine::A, CauseLine::B, CauseLine::C, CauseLine::D]
+        .into_iter()
+        .filter_map(|l| line(l).map(|cause| (l, cause)))
+        .map(|(l, cause)| {
+            json!({
+                "line": l.as_str(),
+                "condition": cause.description,
+                "icd10": cause.icd10,
+                "interval": cause.interval,
+            })
+        })
+        .collect();
+    let part_two: Vec<Value> = death
+        .causes
+        .iter()
+        .filter(|cause| cause.line == CauseLine::Contributing)
+        .map(|cause| json!({ "condition": cause.description, "icd10": cause.icd10, "interval": cause.interval }))
+        .collect();
+    let form = match death.place.place_type {
+        PlaceType::Institution => "Form 4",
+        _ => "Form 4A",
+    };
+
+    json!({
+        "form": form,
+        "title": "Medical Certificate of Cause of Death",
+        "part_one": part_one,
+        "part_two": part_two,
+        "underlying_cause": record.underlying_cause_code,
+        "manner_of_death": manner(death.manner),
+        "autopsy": death.autopsy,
+        "maternal_status": death.maternal_status.map(maternal_status),
+    })
+}
+
+/// Form 2, the Death Report
+fn crs_death(record: &VitalRecord, death: &DeathDetails, deceased: &Patient, offset: FixedOffset) -> Value {
+    let died = record.occurred_at.with_timezone(&offset);
+    let informant = [Some(death.informant.name.clone()), death.informant.address.clone()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+    let underlying = death
+        .causes
+        .iter()
+        .find(|cause| cause.icd10.is_some() && cause.icd10 == record.underlying_cause_code)
+        .or_else(|| death.causes.iter().find(|cause| cause.line == CauseLine::A))
+        .map(cause);
+
+    let mut form = json!({
+        "form": "Form 2",
+        "title": "Death Report",
+        "record_id": record.id,
+        "registration_number": record.registration_number,
+        "legal": [
+            item("1", "Date of death", Some(died.format("%d/%m/%Y %H:%M").to_string())),
+            item("2", "Sex", Some(sex(&deceased.gender).to_string())),
+            item("3", "Name of the deceased", display_name(deceased)),
+            item("4", "Name of the mother", death.mother_name.clone()),
+            item("5", "Name of the father/husband", death.father_or_husband_name.clone()),
+            item("6", "Age of the deceased", deceased.birth_date.and_then(|born| age(born, died.date_naive()))),
+            item("7", "Place of death", Some(place(&death.place))),
+            item("8", "Name and address of the informant", Some(informant)),
+            item("9", "Address of the deceased at the time of death", address(deceased)),
+        ],
+        "statistical": [
+            item("11", "Town or village of residence of the deceased", death.residence.clone()),
+            item("12", "Religion of the deceased", death.religion.clone()),
+            item("13", "Occupation of the deceased", death.occupation.clone()),
+            item(
+                "14",
+                "Type of medical attention received before death",
+                Some(medical_attention(death.medical_attention).to_string()),
+            ),
+            item(
+                "15",
+                "Was cause of death medically certified",
+                Some(if death.medically_certified { "Yes" } else { "No" }.to_string()),
+            ),
+            item("16", "Name of disease or actual cause of death", underlying),
+            item(
+                "17",
+                "In case of female death, whether death occurred while pregnant, at the time of delivery or within \
+                 6 weeks after the end of pregnancy",
+                death.maternal_status.map(|status| maternal_status(status).to_string()),
+            ),
+        ],
+    });
+    if death.medically_certified {
+        form["cause_of_death_certificate"] = crs_cause_certificate(record, death);
+    }
+    form
+}
+
+/// A record as its CRS report form
+pub fn crs_form(record: &VitalRecord, patient: &Patient, offset: FixedOffset) -> Value {
+    match &record.details {
+        VitalRecordDetails::Birth(birth) => crs_birth(record, birth, patient, offset),
+        VitalRecordDetails::Death(death) => crs_death(record, death, patient, offset),
+    }
+}
+
+const BIRTH_COLUMNS: &[&str] = &[
+    "record_id", "registration_number", "status", "date_of_birth", "time_of_birth", "sex", "child_name",
+    "mother_name", "father_name", "place_type", "place_name", "place_address", "delivery_method", "attendant",
+    "birth_weight_grams", "gestation_weeks", "plurality", "informant_name", "informant_address",
+];
+
+const DEATH_COLUMNS: &[&str] = &[
+    "record_id", "registration_number", "status", "date_of_death", "time_of_death", "deceased_name", "sex", "age",
+    "mother_name", "father_or_husband_name", "place_type", "place_name", "place_address", "manner",
+    "medically_certified", "cause_a", "cause_b", "cause_c", "cause_d", "contributing_causes", "underlying_cause",
+    "maternal_status", "informant_name", "informant_address",
+];
+
+/// A field quoted where it holds a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+fn csv_row(record: &VitalRecord, patient: &Patient, offset: FixedOffset) -> Vec<String> {
+    let at = record.occurred_at.with_timezone(&offset);
+    let mut row = vec![
+        record.id.to_string(),
+        record.registration_number.clone().unwrap_or_default(),
+        record.status.as_str().to_string(),
+        at.format("%Y-%m-%d").to_string(),
+        at.format("%H:%M").to_string(),
+    ];
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let number = |value: Option<u32>| value.map(|value| value.to_string()).unwrap_or_default();
+
+    match &record.details {
+        VitalRecordDetails::Birth(birth) => row.extend([
+            sex(&birth.sex).to_string(),
+            text(&birth.child_name),
+            birth.mother_name.clone().or_else(|| display_name(patient)).unwrap_or_default(),
+            text(&birth.father_name),
+            place_type(&birth.place).to_string(),
+            text(&birth.place.name),
+            text(&birth.place.address),
+            delivery_method(birth.delivery_method).to_string(),
+            attendant(birth.attendant).to_string(),
+            number(birth.birth_weight_grams),
+            number(birth.gestation_weeks),
+            birth.plurality.to_string(),
+            birth.informant.name.clone(),
+            text(&birth.informant.address),
+        ]),
+        VitalRecordDetails::Death(death) => {
+            let line = |line: CauseLine| {
+                death
+                    .causes
+                    .iter()
+                    .find(|cause| cause.line == line)
+                    .map(cause)
+                    .unwrap_or_default()
+            };
+            let contributing = death
+                .causes
+                .iter()
+                .filter(|cause| cause.line == CauseLine::Contributing)
+                .map(cause)
+                .collect::<Vec<_>>()
+                .join("; ");
+            row.extend([
+                display_name(patient).unwrap_or_default(),
+                sex(&patient.gender).to_string(),
+                patient
+                    .birth_date
+                    .and_then(|born| age(born, at.date_naive()))
+                    .unwrap_or_default(),
+                text(&death.mother_name),
+                text(&death.father_or_husband_name),
+                place_type(&death.place).to_string(),
+                text(&death.place.name),
+                text(&death.place.address),
+                manner(death.manner).to_string(),
+                if death.medically_certified { "yes" } else { "no" }.to_string(),
+                line(CauseLine::A),
+                line(CauseLine::B),
+                line(CauseLine::C),
+                line(CauseLine::D),
+                contributing,
+                text(&record.underlying_cause_code),
+                death.maternal_status.map(maternal_status).unwrap_or_default().to_string(),
+                death.informant.name.clone(),
+                text(&death.informant.address),
+            ])
+        }
+    }
+    row
+}
+
+/// Export records of one kind, each with its patient: the mother of a birth
+/// or the deceased. Dates and times are given at `offset`.
+pub fn export(
+    kind: VitalRecordKind,
+    records: &[(VitalRecord, Patient)],
+    format: ExportFormat,
+    offset: FixedOffset,
+) -> ExportFile {
+    match format {
+        ExportFormat::Crs => {
+            let forms: Vec<Value> = records
+                .iter()
+                .map(|(record, patient)| crs_form(record, patient, offset))
+                .collect();
+            ExportFile {
+                content_type: "application/json",
+                body: Value::Array(forms).to_string(),
+            }
+        }
+        ExportFormat::Csv => {
+            let columns = match kind {
+                VitalRecordKind::Birth => BIRTH_COLUMNS,
+                VitalRecordKind::Death => DEATH_COLUMNS,
+            };
+            let mut body = columns.join(",");
+            body.push_str("\r\n");
+            for (record, patient) in records {
+                let row: Vec<String> = csv_row(record, patient, offset).iter().map(|field| csv_field(field)).collect();
+                body.push_str(&row.join(","));
+                body.push_str("\r\n");
+            }
+            ExportFile {
+                content_type: "text/csv",
+                body,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HumanName;
+    use crate::modules::vital_record::vital_record_service::{Informant, VitalRecordStatus};
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn patient(gender: Gender, born: Option<NaiveDate>) -> Patient {
+        Patient::new(
+            vec![HumanName {
+                use_type: None,
+                text: None,
+                family: Some("Devi".to_string()),
+                given: vec!["Sita".to_string()],
+                prefix: Vec::new(),
+                suffix: Vec::new(),
+            }],
+            Vec::new(),
+            gender,
+            born,
+        )
+    }
+
+    fn record(patient_id: Uuid, details: VitalRecordDetails, underlying: Option<&str>) -> VitalRecord {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 20, 0, 0).unwrap();
+        VitalRecord {
+            id: Uuid::new_v4(),
+            kind: details.kind(),
+            patient_id,
+            encounter_id: None,
+            occurred_at: now,
+            status: VitalRecordStatus::Final,
+            version: 1,
+            details,
+            underlying_cause_code: underlying.map(str::to_string),
+            registration_number: None,
+            registered_at: None,
+            finalized_at: Some(now),
+            finalized_by: None,
+            created_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn informant() -> Informant {
+        Informant {
+            name: "Ravi Kumar".to_string(),
+            relationship: None,
+            address: Some("12 Main Road, Pune".to_string()),
+        }
+    }
+
+    fn ist() -> FixedOffset {
+        FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap()
+    }
+
+    #[test]
+    fn test_crs_birth() {
+        let mother = patient(Gender::Female, None);
+        let birth = BirthDetails {
+            child_name: None,
+            child_patient_id: None,
+            sex: Gender::Male,
+            place: Place {
+                place_type: PlaceType::Institution,
+                name: Some("District Hospital".to_string()),
+                address: None,
+            },
+            father_name: Some("Ram Kumar".to_string()),
+            mother_name: None,
+            mother_age_years: Some(26),
+            mother_age_at_marriage: None,
+            children_born_alive: Some(2),
+            plurality: 1,
+            delivery_method: DeliveryMethod::Caesarean,
+            attendant: BirthAttendant::InstitutionalGovernment,
+            birth_weight_grams: Some(2950),
+            gestation_weeks: Some(39),
+            informant: informant(),
+            residence: None,
+            religion: None,
+            father_education: None,
+            father_occupation: None,
+            mother_education: None,
+            mother_occupation: None,
+        };
+        let record = record(mother.id, VitalRecordDetails::Birth(birth), None);
+
+        let form = crs_form(&record, &mother, ist());
+        assert_eq!(form["form"], "Form 1");
+        // 20:00 UTC is the next morning in India
+        assert_eq!(form["legal"][0]["value"], "02/03/2024 01:30");
+        assert_eq!(form["legal"][4]["value"], "Sita Devi");
+        assert_eq!(form["legal"][5]["value"], "Hospital/Institution, District Hospital");
+        assert_eq!(form["statistical"][10]["value"], "Caesarean");
+        assert_eq!(form["statistical"][11]["value"], "2.95");
+    }
+
+    #[test]
+    fn test_crs_death() {
+        let deceased = patient(Gender::Female, NaiveDate::from_ymd_opt(1950, 6, 15));
+        let death = DeathDetails {
+            place: Place {
+                place_type: PlaceType::Home,
+                name: None,
+                address: None,
+            },
+            mother_name: None,
+            father_or_husband_name: None,
+            manner: MannerOfDeath::Natural,
+            causes: vec![
+                CauseOfDeath {
+                    line: CauseLine::A,
+                    description: "Pneumonia".to_string(),
+                    icd10: Some("J18.9".to_string()),
+                    interval: Some("5 days".to_string()),
+                },
+                CauseOfDeath {
+                    line: CauseLine::B,
+                    description: "Chronic obstructive pulmonary disease".to_string(),
+                    icd10: Some("J44.9".to_string()),
+                    interval: Some("10 years".to_string()),
+                },
+            ],
+            medical_attention: MedicalAttention::NonInstitutional,
+            medically_certified: true,
+            autopsy: Some(false),
+            maternal_status: None,
+            informant: informant(),
+            residence: None,
+            religion: None,
+            occupation: None,
+        };
+        let record = record(deceased.id, VitalRecordDetails::Death(death), Some("J44.9"));
+
+        let form = crs_form(&record, &deceased, ist());
+        assert_eq!(form["form"], "Form 2");
+        assert_eq!(form["legal"][5]["value"], "73 years");
+        assert_eq!(form["statistical"][5]["value"], "Chronic obstructive pulmonary disease (J44.9)");
+        let certificate = &form["cause_of_death_certificate"];
+        assert_eq!(certificate["form"], "Form 4A");
+        assert_eq!(certificate["part_one"][1]["line"], "b");
+        assert_eq!(certificate["underlying_cause"], "J44.9");
+
+        let csv = export(VitalRecordKind::Death, &[(record, deceased)], ExportFormat::Csv, ist());
+        let lines: Vec<&str> = csv.body.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("record_id,registration_number,status,date_of_death"));
+        assert!(lines[1].contains(",2024-03-02,01:30,Sita Devi,Female,73 years,"));
+        assert!(lines[1].contains(",Pneumonia (J18.9),Chronic obstructive pulmonary disease (J44.9),,,,J44.9,"));
+    }
+
+    #[test]
+    fn test_age_and_csv_field() {
+        let born = NaiveDate::from_ymd_opt(2024, 1, 20).unwrap();
+        assert_eq!(age(born, NaiveDate::from_ymd_opt(2024, 3, 19).unwrap()).unwrap(), "1 months");
+        assert_eq!(age(born, NaiveDate::from_ymd_opt(2024, 2, 5).unwrap()).unwrap(), "16 days");
+        assert_eq!(age(born, NaiveDate::from_ymd_opt(2026, 1, 20).unwrap()).unwrap(), "2 years");
+        assert_eq!(csv_field("Pune, MH"), "\"Pune, MH\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("plain"), "plain");
+    }
+}
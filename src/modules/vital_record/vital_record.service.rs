@@ -0,0 +1,921 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{Gender, Patient};
+use crate::modules::patient::PatientService;
+use crate::modules::vital_record::vital_record_cause::{check_causes, underlying_cause, CauseLine, CauseOfDeath};
+use crate::modules::vital_record::vital_record_export::{export, ExportFile, ExportFormat};
+
+// Import SQL queries
+use crate::modules::vital_record::vital_record_sql::*;
+
+/// Vital record settings
+#[derive(Debug, Clone)]
+pub struct VitalRecordConfig {
+    /// Offset of the local time events are registered in; exported dates
+    /// and times are in it
+    pub utc_offset: FixedOffset,
+}
+
+impl Default for VitalRecordConfig {
+    fn default() -> Self {
+        Self {
+            utc_offset: FixedOffset::east_opt(0).expect("UTC is a valid offset"),
+        }
+    }
+}
+
+impl VitalRecordConfig {
+    /// Settings from `VITAL_RECORDS_UTC_OFFSET`, e.g. `+05:30`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            utc_offset: std::env::var("VITAL_RECORDS_UTC_OFFSET")
+                .ok()
+                .and_then(|offset| offset.trim().parse().ok())
+                .unwrap_or(defaults.utc_offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VitalRecordKind {
+    Birth,
+    Death,
+}
+
+impl VitalRecordKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VitalRecordKind::Birth => "birth",
+            VitalRecordKind::Death => "death",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "birth" => Some(VitalRecordKind::Birth),
+            "death" => Some(VitalRecordKind::Death),
+            _ => None,
+        }
+    }
+}
+
+/// Where a record is in its life: drafts are edited freely, final records
+/// are only changed by amendment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VitalRecordStatus {
+    Draft,
+    Final,
+    Amended,
+}
+
+impl VitalRecordStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VitalRecordStatus::Draft => "draft",
+            VitalRecordStatus::Final => "final",
+            VitalRecordStatus::Amended => "amended",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "draft" => Some(VitalRecordStatus::Draft),
+            "final" => Some(VitalRecordStatus::Final),
+            "amended" => Some(VitalRecordStatus::Amended),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaceType {
+    /// A hospital or other health institution
+    Institution,
+    Home,
+    Other,
+}
+
+/// Where a birth or death took place
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Place {
+    #[serde(rename = "type")]
+    pub place_type: PlaceType,
+    /// The institution's name
+    pub name: Option<String>,
+    pub address: Option<String>,
+}
+
+/// Who gave notice of the event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Informant {
+    pub name: String,
+    pub relationship: Option<String>,
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMethod {
+    Natural,
+    Caesarean,
+    ForcepsVacuum,
+}
+
+/// Who attended a delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BirthAttendant {
+    InstitutionalGovernment,
+    InstitutionalPrivate,
+    DoctorNurseMidwife,
+    TraditionalBirthAttendant,
+    RelativesOrOthers,
+}
+
+fn single() -> u32 {
+    1
+}
+
+/// Particulars of a birth; the record's patient is the mother
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BirthDetails {
+    pub child_name: Option<String>,
+    /// The child, once registered as a patient
+    pub child_patient_id: Option<Uuid>,
+    pub sex: Gender,
+    pub place: Place,
+    pub father_name: Option<String>,
+    /// As registered; the mother's patient name when not given
+    pub mother_name: Option<String>,
+    pub mother_age_years: Option<u32>,
+    pub mother_age_at_marriage: Option<u32>,
+    /// Children born alive to the mother, this one included
+    pub children_born_alive: Option<u32>,
+    /// Children born of this delivery
+    #[serde(default = "single")]
+    pub plurality: u32,
+    pub delivery_method: DeliveryMethod,
+    pub attendant: BirthAttendant,
+    pub birth_weight_grams: Option<u32>,
+    pub gestation_weeks: Option<u32>,
+    pub informant: Informant,
+    /// Town or village of the mother's usual residence
+    pub residence: Option<String>,
+    pub religion: Option<String>,
+    pub father_education: Option<String>,
+    pub father_occupation: Option<String>,
+    pub mother_education: Option<String>,
+    pub mother_occupation: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MannerOfDeath {
+    Natural,
+    Accident,
+    Suicide,
+    Homicide,
+    PendingInvestigation,
+    Undetermined,
+}
+
+/// Medical attention the deceased received before death
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MedicalAttention {
+    Institutional,
+    NonInstitutional,
+    None,
+}
+
+/// Whether a woman died while pregnant, in delivery or soon after
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaternalStatus {
+    NotPregnant,
+    DuringPregnancy,
+    DuringDelivery,
+    WithinSixWeeks,
+}
+
+/// Particulars of a death; the record's patient is the deceased
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeathDetails {
+    pub place: Place,
+    pub mother_name: Option<String>,
+    pub father_or_husband_name: Option<String>,
+    pub manner: MannerOfDeath,
+    /// The medical certificate of cause of death, Parts I and II
+    #[serde(default)]
+    pub causes: Vec<CauseOfDeath>,
+    pub medical_attention: MedicalAttention,
+    pub medically_certified: bool,
+    pub autopsy: Option<bool>,
+    pub maternal_status: Option<MaternalStatus>,
+    pub informant: Informant,
+    pub residence: Option<String>,
+    pub religion: Option<String>,
+    pub occupation: Option<String>,
+}
+
+/// Particulars of a birth or a death
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VitalRecordDetails {
+    Birth(BirthDetails),
+    Death(DeathDetails),
+}
+
+fn check_range(name: &str, value: Option<u32>, range: std::ops::RangeInclusive<u32>) -> Result<(), String> {
+    match value {
+        Some(value) if !range.contains(&value) => Err(format!(
+            "{} of {} is outside {} to {}",
+            name,
+            value,
+            range.start(),
+            range.end()
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl VitalRecordDetails {
+    pub fn kind(&self) -> VitalRecordKind {
+        match self {
+            VitalRecordDetails::Birth(_) => VitalRecordKind::Birth,
+            VitalRecordDetails::Death(_) => VitalRecordKind::Death,
+        }
+    }
+
+    /// Normalize the particulars and check them, as any draft must pass
+    pub fn check(&mut self) -> Result<(), String> {
+        match self {
+            VitalRecordDetails::Birth(birth) => {
+                if birth.informant.name.trim().is_empty() {
+                    return Err("A birth needs an informant".to_string());
+                }
+                check_range("Birth weight (g)", birth.birth_weight_grams, 100..=8000)?;
+                check_range("Gestation (weeks)", birth.gestation_weeks, 20..=45)?;
+                check_range("Plurality", Some(birth.plurality), 1..=8)?;
+                check_range("Mother's age", birth.mother_age_years, 10..=70)?;
+                check_range("Mother's age at marriage", birth.mother_age_at_marriage, 10..=70)?;
+                check_range("Children born alive", birth.children_born_alive, 1..=30)
+            }
+            VitalRecordDetails::Death(death) => {
+                if death.informant.name.trim().is_empty() {
+                    return Err("A death needs an informant".to_string());
+                }
+                check_causes(&mut death.causes)
+            }
+        }
+    }
+
+    /// Check the particulars are complete enough to register, for the
+    /// record's patient and date of the event
+    pub fn check_complete(&self, patient: &Patient, occurred_at: DateTime<Utc>) -> Result<(), String> {
+        match self {
+            VitalRecordDetails::Birth(birth) => {
+                if matches!(patient.gender, Gender::Male) {
+                    return Err(format!("Patient {} is not recorded as female and cannot be the mother", patient.id));
+                }
+                if birth.place.place_type == PlaceType::Institution && birth.birth_weight_grams.is_none() {
+                    return Err("An institutional birth needs the birth weight".to_string());
+                }
+                Ok(())
+            }
+            VitalRecordDetails::Death(death) => {
+                if patient.birth_date.is_some_and(|born| occurred_at.date_naive() < born) {
+                    return Err(format!("Patient {} was born after the date of death", patient.id));
+                }
+                if death.maternal_status.is_some() && matches!(patient.gender, Gender::Male) {
+                    return Err("Maternal status is only recorded for women".to_string());
+                }
+                if death.medically_certified {
+                    if !death.causes.iter().any(|cause| cause.line == CauseLine::A) {
+                        return Err("A medically certified death needs its immediate cause on Part I line (a)".to_string());
+                    }
+                    if let Some(uncoded) = death
+                        .causes
+                        .iter()
+                        .find(|cause| cause.line != CauseLine::Contributing && cause.icd10.is_none())
+                    {
+                        return Err(format!("Part I line {} needs an ICD-10 code", uncoded.line.as_str()));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// ICD-10 underlying cause of a death
+    pub fn underlying_cause(&self) -> Option<String> {
+        match self {
+            VitalRecordDetails::Birth(_) => None,
+            VitalRecordDetails::Death(death) => underlying_cause(&death.causes, death.manner == MannerOfDeath::Natural),
+        }
+    }
+}
+
+/// A birth or death notification
+#[derive(Debug, Clone, Serialize)]
+pub struct VitalRecord {
+    pub id: Uuid,
+    pub kind: VitalRecordKind,
+    /// The mother of a birth, or the deceased
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub occurred_at: DateTime<Utc>,
+    pub status: VitalRecordStatus,
+    /// 1 until first amended
+    pub version: i32,
+    pub details: VitalRecordDetails,
+    pub underlying_cause_code: Option<String>,
+    pub registration_number: Option<String>,
+    pub registered_at: Option<DateTime<Utc>>,
+    pub finalized_at: Option<DateTime<Utc>>,
+    pub finalized_by: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn record_from_row(row: &PgRow) -> Result<VitalRecord, HimsError> {
+    let id: Uuid = row.get("id");
+    let details = serde_json::from_value(row.get("details")).map_err(|e| HimsError::InternalError {
+        message: format!("Vital record {} has malformed details: {}", id, e),
+    })?;
+
+    Ok(VitalRecord {
+        id,
+        kind: VitalRecordKind::from_string(row.get("kind")).unwrap_or(VitalRecordKind::Birth),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        occurred_at: row.get("occurred_at"),
+        status: VitalRecordStatus::from_string(row.get("status")).unwrap_or(VitalRecordStatus::Draft),
+        version: row.get("version"),
+        details,
+        underlying_cause_code: row.get("underlying_cause_code"),
+        registration_number: row.get("registration_number"),
+        registered_at: row.get("registered_at"),
+        finalized_at: row.get("finalized_at"),
+        finalized_by: row.get("finalized_by"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+/// An amendment of a finalized record
+#[derive(Debug, Clone, Serialize)]
+pub struct VitalRecordAmendment {
+    pub id: Uuid,
+    pub vital_record_id: Uuid,
+    /// Record version the amendment produced
+    pub version: i32,
+    pub reason: String,
+    /// The record's particulars before the amendment
+    pub previous: serde_json::Value,
+    pub amended_by: Option<Uuid>,
+    pub amended_at: DateTime<Utc>,
+}
+
+fn amendment_from_row(row: &PgRow) -> VitalRecordAmendment {
+    VitalRecordAmendment {
+        id: row.get("id"),
+        vital_record_id: row.get("vital_record_id"),
+        version: row.get("version"),
+        reason: row.get("reason"),
+        previous: row.get("previous"),
+        amended_by: row.get("amended_by"),
+        amended_at: row.get("amended_at"),
+    }
+}
+
+/// A birth or death notification as clients send it
+#[derive(Debug, Clone, Deserialize)]
+pub struct VitalRecordRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    pub occurred_at: DateTime<Utc>,
+    pub details: VitalRecordDetails,
+}
+
+/// A finalized record's corrected particulars, and why they were corrected
+#[derive(Debug, Clone, Deserialize)]
+pub struct AmendRequest {
+    pub reason: String,
+    #[serde(flatten)]
+    pub record: VitalRecordRequest,
+}
+
+/// Which records to list
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VitalRecordQuery {
+    pub kind: Option<VitalRecordKind>,
+    pub status: Option<VitalRecordStatus>,
+    pub patient_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+fn database_error(e: sqlx::Error) -> HimsError {
+    HimsError::DatabaseError(e.to_string())
+}
+
+/// Database error, or a conflict when a unique constraint was violated
+fn write_error(e: sqlx::Error, conflict: impl FnOnce() -> String) -> HimsError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => HimsError::ConflictError { message: conflict() },
+        _ => database_error(e),
+    }
+}
+
+fn json_value<T: Serialize>(value: &T) -> Result<serde_json::Value, HimsError> {
+    serde_json::to_value(value).map_err(|e| HimsError::InternalError {
+        message: format!("Failed to serialize vital record: {}", e),
+    })
+}
+
+/// Vital record service for birth and death notifications, their
+/// amendment and their export to civil registration
+pub struct VitalRecordService {
+    pool: PgPool,
+    patients: Arc<PatientService>,
+    config: VitalRecordConfig,
+}
+
+impl VitalRecordService {
+    /// Create new vital record service
+    pub fn new(pool: PgPool, patients: Arc<PatientService>, config: VitalRecordConfig) -> Self {
+        Self { pool, patients, config }
+    }
+
+    async fn patient(&self, id: Uuid) -> Result<Patient, HimsError> {
+        self.patients
+            .get_patient(id)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .ok_or_else(|| HimsError::ValidationError {
+                message: format!("Patient {} does not exist", id),
+            })
+    }
+
+    /// Check an encounter a record is linked to is of the record's patient
+    async fn check_encounter(conn: &mut PgConnection, encounter_id: Uuid, patient_id: Uuid) -> Result<(), HimsError> {
+        let subject: Option<Uuid> = sqlx::query(GET_ENCOUNTER_SUBJECT)
+            .bind(encounter_id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?
+            .map(|row| row.get("subject"));
+        match subject {
+            Some(subject) if subject == patient_id => Ok(()),
+            Some(_) => Err(HimsError::ValidationError {
+                message: format!("Encounter {} is not of patient {}", encounter_id, patient_id),
+            }),
+            None => Err(HimsError::ValidationError {
+                message: format!("Encounter {} does not exist", encounter_id),
+            }),
+        }
+    }
+
+    /// Check a notification as any draft must pass, normalizing its
+    /// particulars
+    async fn check_request(&self, conn: &mut PgConnection, request: &mut VitalRecordRequest) -> Result<(), HimsError> {
+        if request.occurred_at > Utc::now() {
+            return Err(HimsError::ValidationError {
+                message: "A birth or death cannot be notified before it happens".to_string(),
+            });
+        }
+        request
+            .details
+            .check()
+            .map_err(|message| HimsError::ValidationError { message })?;
+        self.patient(request.patient_id).await?;
+        if let Some(encounter_id) = request.encounter_id {
+            Self::check_encounter(conn, encounter_id, request.patient_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Create a draft birth or death record
+    pub async fn create_record(
+        &self,
+        mut request: VitalRecordRequest,
+        created_by: Option<Uuid>,
+    ) -> Result<VitalRecord, HimsError> {
+        let mut conn = self.pool.acquire().await.map_err(database_error)?;
+        self.check_request(&mut conn, &mut request).await?;
+
+        let row = sqlx::query(INSERT_RECORD)
+            .bind(Uuid::new_v4())
+            .bind(request.details.kind().as_str())
+            .bind(request.patient_id)
+            .bind(request.encounter_id)
+            .bind(request.occurred_at)
+            .bind(json_value(&request.details)?)
+            .bind(request.details.underlying_cause())
+            .bind(created_by)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(database_error)?;
+        record_from_row(&row)
+    }
+
+    /// Get vital record by ID
+    pub async fn get_record(&self, id: Uuid) -> Result<Option<VitalRecord>, HimsError> {
+        let row = sqlx::query(GET_RECORD_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+        row.as_ref().map(record_from_row).transpose()
+    }
+
+    /// The 500 records of the latest events matching a query
+    pub async fn list_records(&self, query: &VitalRecordQuery) -> Result<Vec<VitalRecord>, HimsError> {
+        let rows = sqlx::query(LIST_RECORDS)
+            .bind(query.kind.map(|kind| kind.as_str()))
+            .bind(query.status.map(|status| status.as_str()))
+            .bind(query.patient_id)
+            .bind(query.from)
+            .bind(query.to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        rows.iter().map(record_from_row).collect()
+    }
+
+    async fn lock_record(conn: &mut PgConnection, id: Uuid) -> Result<Option<VitalRecord>, HimsError> {
+        let row = sqlx::query(LOCK_RECORD)
+            .bind(id)
+            .fetch_optional(conn)
+            .await
+            .map_err(database_error)?;
+        row.as_ref().map(record_from_row).transpose()
+    }
+
+    async fn save(conn: &mut PgConnection, record: &VitalRecord) -> Result<(), HimsError> {
+        sqlx::query(UPDATE_RECORD)
+            .bind(record.id)
+            .bind(record.patient_id)
+            .bind(record.encounter_id)
+            .bind(record.occurred_at)
+            .bind(json_value(&record.details)?)
+            .bind(&record.underlying_cause_code)
+            .bind(record.status.as_str())
+            .bind(record.version)
+            .execute(conn)
+            .await
+            .map_err(database_error)?;
+        Ok(())
+    }
+
+    /// Replace a draft's particulars. `None` if there is no such record.
+    pub async fn update_record(
+        &self,
+        id: Uuid,
+        mut request: VitalRecordRequest,
+    ) -> Result<Option<VitalRecord>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut record) = Self::lock_record(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        if record.status != VitalRecordStatus::Draft {
+            return Err(HimsError::ConflictError {
+                message: format!("Vital record {} is {}; change it by amendment", id, record.status.as_str()),
+            });
+        }
+        if request.details.kind() != record.kind {
+            return Err(HimsError::ValidationError {
+                message: format!("Vital record {} is of a {}", id, record.kind.as_str()),
+            });
+        }
+        self.check_request(&mut tx, &mut request).await?;
+
+        record.underlying_cause_code = request.details.underlying_cause();
+        record.patient_id = request.patient_id;
+        record.encounter_id = request.encounter_id;
+        record.occurred_at = request.occurred_at;
+        record.details = request.details;
+        Self::save(&mut tx, &record).await?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_record(id).await
+    }
+
+    /// Finalize a draft once its particulars are complete, after which it is
+    /// only changed by amendment. Finalizing a death marks the patient as
+    /// deceased. `None` if there is no such record.
+    pub async fn finalize_record(&self, id: Uuid, finalized_by: Option<Uuid>) -> Result<Option<VitalRecord>, HimsError> {
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(record) = Self::lock_record(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        if record.status != VitalRecordStatus::Draft {
+            return Err(HimsError::ConflictError {
+                message: format!("Vital record {} is already {}", id, record.status.as_str()),
+            });
+        }
+        let patient = self.patient(record.patient_id).await?;
+        record
+            .details
+            .check_complete(&patient, record.occurred_at)
+            .map_err(|message| HimsError::ValidationError { message })?;
+
+        sqlx::query(FINALIZE_RECORD)
+            .bind(id)
+            .bind(finalized_by)
+            .execute(&mut *tx)
+            .await
+            .map_err(database_error)?;
+        if record.kind == VitalRecordKind::Death {
+            sqlx::query(MARK_PATIENT_DECEASED)
+                .bind(record.patient_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+        self.get_record(id).await
+    }
+
+    /// Amend a finalized record, keeping the particulars it replaces with
+    /// the reason for the amendment. `None` if there is no such record.
+    pub async fn amend_record(
+        &self,
+        id: Uuid,
+        mut request: AmendRequest,
+        amended_by: Option<Uuid>,
+    ) -> Result<Option<VitalRecord>, HimsError> {
+        if request.reason.trim().is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "An amendment needs a reason".to_string(),
+            });
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(mut record) = Self::lock_record(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        if record.status == VitalRecordStatus::Draft {
+            return Err(HimsError::ConflictError {
+                message: format!("Vital record {} is a draft; edit it instead", id),
+            });
+        }
+        if request.record.details.kind() != record.kind {
+            return Err(HimsError::ValidationError {
+                message: format!("Vital record {} is of a {}", id, record.kind.as_str()),
+            });
+        }
+        self.check_request(&mut tx, &mut request.record).await?;
+        let patient = self.patient(request.record.patient_id).await?;
+        request
+            .record
+            .details
+            .check_complete(&patient, request.record.occurred_at)
+            .map_err(|message| HimsError::ValidationError { message })?;
+
+        let previous = serde_json::json!({
+            "patient_id": record.patient_id,
+            "encounter_id": record.encounter_id,
+            "occurred_at": record.occurred_at,
+            "details": record.details,
+            "underlying_cause_code": record.underlying_cause_code,
+        });
+        record.version += 1;
+        sqlx::query(INSERT_AMENDMENT)
+            .bind(id)
+            .bind(record.version)
+            .bind(request.reason.trim())
+            .bind(previous)
+            .bind(amended_by)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| write_error(e, || format!("Vital record {} was amended concurrently", id)))?;
+
+        record.underlying_cause_code = request.record.details.underlying_cause();
+        record.patient_id = request.record.patient_id;
+        record.encounter_id = request.record.encounter_id;
+        record.occurred_at = request.record.occurred_at;
+        record.details = request.record.details;
+        record.status = VitalRecordStatus::Amended;
+        Self::save(&mut tx, &record).await?;
+        if record.kind == VitalRecordKind::Death {
+            sqlx::query(MARK_PATIENT_DECEASED)
+                .bind(record.patient_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(database_error)?;
+        }
+        tx.commit().await.map_err(database_error)?;
+        self.get_record(id).await
+    }
+
+    /// Amendments of a record, oldest first
+    pub async fn list_amendments(&self, id: Uuid) -> Result<Vec<VitalRecordAmendment>, HimsError> {
+        let rows = sqlx::query(LIST_AMENDMENTS)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        Ok(rows.iter().map(amendment_from_row).collect())
+    }
+
+    /// Record the number the civil registry registered a finalized record
+    /// under. `None` if there is no such record.
+    pub async fn register_record(&self, id: Uuid, registration_number: String) -> Result<Option<VitalRecord>, HimsError> {
+        let registration_number = registration_number.trim().to_string();
+        if registration_number.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "A registration number is required".to_string(),
+            });
+        }
+
+        let mut tx = self.pool.begin().await.map_err(database_error)?;
+        let Some(record) = Self::lock_record(&mut tx, id).await? else {
+            return Ok(None);
+        };
+        if record.status == VitalRecordStatus::Draft {
+            return Err(HimsError::ConflictError {
+                message: format!("Vital record {} is a draft; finalize it before registering it", id),
+            });
+        }
+        if let Some(registered) = &record.registration_number {
+            return Err(HimsError::ConflictError {
+                message: format!("Vital record {} is already registered as {}", id, registered),
+            });
+        }
+
+        sqlx::query(REGISTER_RECORD)
+            .bind(id)
+            .bind(&registration_number)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                write_error(e, || {
+                    format!("Another {} is registered as {}", record.kind.as_str(), registration_number)
+                })
+            })?;
+        tx.commit().await.map_err(database_error)?;
+        self.get_record(id).await
+    }
+
+    async fn export_records(
+        &self,
+        kind: VitalRecordKind,
+        records: Vec<VitalRecord>,
+        format: ExportFormat,
+    ) -> Result<ExportFile, HimsError> {
+        let mut exported = Vec::with_capacity(records.len());
+        for record in records {
+            let patient = self.patient(record.patient_id).await?;
+            exported.push((record, patient));
+        }
+        Ok(export(kind, &exported, format, self.config.utc_offset))
+    }
+
+    /// Export a finalized record for civil registration. `None` if there is
+    /// no such record.
+    pub async fn export_record(&self, id: Uuid, format: ExportFormat) -> Result<Option<ExportFile>, HimsError> {
+        let Some(record) = self.get_record(id).await? else {
+            return Ok(None);
+        };
+        if record.status == VitalRecordStatus::Draft {
+            return Err(HimsError::ConflictError {
+                message: format!("Vital record {} is a draft; only finalized records are exported", id),
+            });
+        }
+        self.export_records(record.kind, vec![record], format).await.map(Some)
+    }
+
+    /// Export the finalized records of one kind of events in [from, to)
+    pub async fn export_period(
+        &self,
+        kind: VitalRecordKind,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        format: ExportFormat,
+    ) -> Result<ExportFile, HimsError> {
+        if from >= to {
+            return Err(HimsError::ValidationError {
+                message: "An export period must end after it starts".to_string(),
+            });
+        }
+        let rows = sqlx::query(GET_EXPORT_RECORDS)
+            .bind(kind.as_str())
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+        let records = rows.iter().map(record_from_row).collect::<Result<Vec<_>, _>>()?;
+        self.export_records(kind, records, format).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn informant() -> Informant {
+        Informant {
+            name: "Ravi Kumar".to_string(),
+            relationship: Some("Son".to_string()),
+            address: None,
+        }
+    }
+
+    fn death() -> DeathDetails {
+        DeathDetails {
+            place: Place {
+                place_type: PlaceType::Institution,
+                name: Some("District Hospital".to_string()),
+                address: None,
+            },
+            mother_name: None,
+            father_or_husband_name: None,
+            manner: MannerOfDeath::Natural,
+            causes: vec![CauseOfDeath {
+                line: CauseLine::A,
+                description: "Acute myocardial infarction".to_string(),
+                icd10: Some("i219".to_string()),
+                interval: Some("2 hours".to_string()),
+            }],
+            medical_attention: MedicalAttention::Institutional,
+            medically_certified: true,
+            autopsy: None,
+            maternal_status: None,
+            informant: informant(),
+            residence: None,
+            religion: None,
+            occupation: None,
+        }
+    }
+
+    #[test]
+    fn test_details_serde() {
+        let json = serde_json::json!({
+            "kind": "birth",
+            "sex": "female",
+            "place": { "type": "institution", "name": "District Hospital" },
+            "delivery_method": "caesarean",
+            "attendant": "institutional_government",
+            "birth_weight_grams": 2900,
+            "informant": { "name": "Ravi Kumar" }
+        });
+        let details: VitalRecordDetails = serde_json::from_value(json).unwrap();
+        assert_eq!(details.kind(), VitalRecordKind::Birth);
+        let VitalRecordDetails::Birth(birth) = &details else {
+            panic!("expected a birth");
+        };
+        assert_eq!(birth.plurality, 1);
+        assert_eq!(birth.delivery_method, DeliveryMethod::Caesarean);
+        assert_eq!(serde_json::to_value(&details).unwrap()["kind"], "birth");
+    }
+
+    #[test]
+    fn test_check_details() {
+        let mut details = VitalRecordDetails::Death(death());
+        assert!(details.check().is_ok());
+        assert_eq!(details.underlying_cause().as_deref(), Some("I21.9"));
+
+        let mut no_informant = death();
+        no_informant.informant.name = " ".to_string();
+        assert!(VitalRecordDetails::Death(no_informant).check().is_err());
+    }
+
+    #[test]
+    fn test_check_complete() {
+        let mut patient = Patient::new(Vec::new(), Vec::new(), Gender::Male, NaiveDate::from_ymd_opt(1950, 1, 1));
+        let died = Utc.with_ymd_and_hms(2024, 3, 1, 4, 0, 0).unwrap();
+        let mut details = VitalRecordDetails::Death(death());
+        details.check().unwrap();
+        assert!(details.check_complete(&patient, died).is_ok());
+
+        let mut uncoded = death();
+        uncoded.causes[0].icd10 = None;
+        assert!(VitalRecordDetails::Death(uncoded.clone()).check_complete(&patient, died).is_err());
+        uncoded.medically_certified = false;
+        assert!(VitalRecordDetails::Death(uncoded).check_complete(&patient, died).is_ok());
+
+        let mut maternal = death();
+        maternal.maternal_status = Some(MaternalStatus::WithinSixWeeks);
+        assert!(VitalRecordDetails::Death(maternal.clone()).check_complete(&patient, died).is_err());
+        patient.gender = Gender::Female;
+        assert!(VitalRecordDetails::Death(maternal).check_complete(&patient, died).is_ok());
+
+        let before_birth = Utc.with_ymd_and_hms(1949, 3, 1, 4, 0, 0).unwrap();
+        assert!(details.check_complete(&patient, before_birth).is_err());
+    }
+}
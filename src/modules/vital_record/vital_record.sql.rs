@@ -0,0 +1,106 @@
+//! Vital Record SQL Queries
+//!
+//! This file contains all SQL queries used by the vital record service.
+
+/// Create a draft birth or death record
+pub const INSERT_RECORD: &str = r#"
+    INSERT INTO vital_records (id, kind, patient_id, encounter_id, occurred_at, details, underlying_cause_code,
+                               created_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING id, kind, patient_id, encounter_id, occurred_at, status, version, details, underlying_cause_code,
+              registration_number, registered_at, finalized_at, finalized_by, created_by, created_at, updated_at
+"#;
+
+/// Get vital record by ID
+pub const GET_RECORD_BY_ID: &str = r#"
+    SELECT id, kind, patient_id, encounter_id, occurred_at, status, version, details, underlying_cause_code,
+           registration_number, registered_at, finalized_at, finalized_by, created_by, created_at, updated_at
+    FROM vital_records
+    WHERE id = $1
+"#;
+
+/// Lock a record while it is changed
+pub const LOCK_RECORD: &str = r#"
+    SELECT id, kind, patient_id, encounter_id, occurred_at, status, version, details, underlying_cause_code,
+           registration_number, registered_at, finalized_at, finalized_by, created_by, created_at, updated_at
+    FROM vital_records
+    WHERE id = $1
+    FOR UPDATE
+"#;
+
+/// The newest records, optionally of one kind ($1), in one status ($2), of
+/// one patient ($3) or of events in [$4, $5)
+pub const LIST_RECORDS: &str = r#"
+    SELECT id, kind, patient_id, encounter_id, occurred_at, status, version, details, underlying_cause_code,
+           registration_number, registered_at, finalized_at, finalized_by, created_by, created_at, updated_at
+    FROM vital_records
+    WHERE ($1::text IS NULL OR kind = $1)
+      AND ($2::text IS NULL OR status = $2)
+      AND ($3::uuid IS NULL OR patient_id = $3)
+      AND ($4::timestamptz IS NULL OR occurred_at >= $4)
+      AND ($5::timestamptz IS NULL OR occurred_at < $5)
+    ORDER BY occurred_at DESC
+    LIMIT 500
+"#;
+
+/// Finalized records of one kind ($1) of events in [$2, $3), for export
+pub const GET_EXPORT_RECORDS: &str = r#"
+    SELECT id, kind, patient_id, encounter_id, occurred_at, status, version, details, underlying_cause_code,
+           registration_number, registered_at, finalized_at, finalized_by, created_by, created_at, updated_at
+    FROM vital_records
+    WHERE kind = $1
+      AND status IN ('final', 'amended')
+      AND occurred_at >= $2
+      AND occurred_at < $3
+    ORDER BY occurred_at
+"#;
+
+/// Save a record's particulars, status and version
+pub const UPDATE_RECORD: &str = r#"
+    UPDATE vital_records
+    SET patient_id = $2, encounter_id = $3, occurred_at = $4, details = $5, underlying_cause_code = $6,
+        status = $7, version = $8
+    WHERE id = $1
+"#;
+
+/// Finalize a draft record
+pub const FINALIZE_RECORD: &str = r#"
+    UPDATE vital_records
+    SET status = 'final', finalized_at = NOW(), finalized_by = $2
+    WHERE id = $1 AND status = 'draft'
+"#;
+
+/// Record the civil registry's registration number of a finalized record
+pub const REGISTER_RECORD: &str = r#"
+    UPDATE vital_records
+    SET registration_number = $2, registered_at = NOW()
+    WHERE id = $1
+"#;
+
+/// Record an amendment and the particulars it replaced
+pub const INSERT_AMENDMENT: &str = r#"
+    INSERT INTO vital_record_amendments (vital_record_id, version, reason, previous, amended_by)
+    VALUES ($1, $2, $3, $4, $5)
+"#;
+
+/// Amendments of a record, oldest first
+pub const LIST_AMENDMENTS: &str = r#"
+    SELECT id, vital_record_id, version, reason, previous, amended_by, amended_at
+    FROM vital_record_amendments
+    WHERE vital_record_id = $1
+    ORDER BY version
+"#;
+
+/// Mark the patient a death was certified of as deceased
+pub const MARK_PATIENT_DECEASED: &str = r#"
+    UPDATE patients
+    SET deceased = TRUE
+    WHERE id = $1
+"#;
+
+/// Patient an encounter is of
+pub const GET_ENCOUNTER_SUBJECT: &str = r#"
+    SELECT subject
+    FROM encounters
+    WHERE id = $1
+"#;
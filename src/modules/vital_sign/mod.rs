@@ -0,0 +1,64 @@
+//! Vital Sign Module
+//!
+//! This module provides vital signs capture including:
+//! - Observations conforming to the FHIR vital-signs profiles, coded in LOINC
+//! - UCUM units validated per sign, with plausible value ranges
+//! - Blood pressure as systolic and diastolic components
+//! - The device a measurement came from
+//! - Time-bucketed trend series per patient for charting
+//! - Access checked against the patient, and every access audited
+
+#[path = "vital_sign.controller.rs"]
+pub mod vital_sign_controller;
+#[path = "vital_sign.service.rs"]
+pub mod vital_sign_service;
+#[path = "vital_sign.sql.rs"]
+pub mod vital_sign_sql;
+#[path = "vital_sign.trends.rs"]
+pub mod vital_sign_trends;
+#[path = "vital_sign.units.rs"]
+pub mod vital_sign_units;
+
+pub use vital_sign_controller::VitalSignController;
+pub use vital_sign_service::{VitalSignRequest, VitalSignSearch, VitalSignSearchResult, VitalSignService};
+pub use vital_sign_trends::{TrendBucket, TrendQuery, TrendSeries, VitalSignTrends};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::modules::audit::AuditService;
+use crate::modules::authorization::HimsAuthorizationEngine;
+use crate::modules::events::EventBus;
+use crate::utils::api_router::ApiRouter;
+
+/// Vital Sign Module Configuration
+pub struct VitalSignModule {
+    pub service: Arc<VitalSignService>,
+    pub controller: Arc<VitalSignController>,
+}
+
+impl VitalSignModule {
+    /// Create a new Vital Sign Module with dependency injection
+    pub fn new(db_pool: PgPool, events: EventBus, authorization_engine: Arc<HimsAuthorizationEngine>) -> Self {
+        let audit_service = Arc::new(AuditService::new(db_pool.clone()));
+        let service = Arc::new(VitalSignService::with_events(db_pool, events));
+        let controller = Arc::new(VitalSignController::new(service.clone(), authorization_engine, audit_service));
+
+        Self { service, controller }
+    }
+
+    /// Register routes for this module
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Trend routes, mounted under the patient routes
+    pub fn trend_routes(&self) -> ApiRouter {
+        self.controller.trend_routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<VitalSignService> {
+        self.service.clone()
+    }
+}
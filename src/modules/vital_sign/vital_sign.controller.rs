@@ -0,0 +1,483 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::constants::{LOINC_SYSTEM, OBSERVATION_CATEGORY_SYSTEM, OBSERVATION_RESOURCE_TYPE};
+use crate::models::{
+    AuditAction, AuditEventType, AuditLog, CodeableConcept, Coding, ObservationStatus, Quantity, Reference,
+    ResourceMeta, VitalSign, VitalSignKind,
+};
+use crate::modules::audit::AuditService;
+use crate::modules::auth::AuthContext;
+use crate::modules::authorization::{Action, HimsAuthorizationEngine, Resource};
+use crate::modules::graphql::graphql_authz::FieldAuthorizer;
+use crate::modules::vital_sign::vital_sign_service::{VitalSignRequest, VitalSignSearch, VitalSignSearchResult};
+use crate::modules::vital_sign::vital_sign_trends::{TrendQuery, VitalSignTrends};
+use crate::modules::vital_sign::VitalSignService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::etag::{if_match_version, precondition_status, versioned, Versioned};
+use crate::utils::fhir_search::{EntrySearch, SearchEntryMode};
+use crate::utils::pagination::{page_links, BundleLink};
+
+/// FHIR R4 Observation of the vital-signs profile, as served
+#[derive(Debug, Serialize)]
+pub struct VitalSignResponse {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    /// The measuring device, when it is not a registered Device
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Value>,
+    pub status: ObservationStatus,
+    pub category: Vec<CodeableConcept>,
+    pub code: CodeableConcept,
+    pub subject: Reference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(rename = "effectiveDateTime")]
+    pub effective_date_time: DateTime<Utc>,
+    pub issued: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "valueQuantity")]
+    pub value_quantity: Option<Quantity>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<Reference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub component: Vec<VitalSignComponentResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VitalSignComponentResponse {
+    pub code: CodeableConcept,
+    #[serde(rename = "valueQuantity")]
+    pub value_quantity: Quantity,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VitalSignBundle {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub id: Uuid,
+    pub meta: ResourceMeta,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    /// Matches across all pages; omitted for `_total=none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    /// `self`, and `next` and `previous` pages where there are any
+    pub link: Vec<BundleLink>,
+    pub entry: Vec<VitalSignBundleEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VitalSignBundleEntry {
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    pub resource: VitalSignResponse,
+    pub search: EntrySearch,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Vital sign controller for capturing measurements and charting trends.
+/// Vital signs are part of their patient's chart: reading them takes read
+/// access to the patient and recording, correcting or removing them update
+/// access, and each is audited against the patient.
+pub struct VitalSignController {
+    state: VitalSignState,
+}
+
+#[derive(Clone)]
+pub struct VitalSignState {
+    service: Arc<VitalSignService>,
+    authorization_engine: Arc<HimsAuthorizationEngine>,
+    audit_service: Arc<AuditService>,
+}
+
+impl VitalSignController {
+    /// Create new controller with injected services
+    pub fn new(
+        vital_sign_service: Arc<VitalSignService>,
+        authorization_engine: Arc<HimsAuthorizationEngine>,
+        audit_service: Arc<AuditService>,
+    ) -> Self {
+        Self {
+            state: VitalSignState {
+                service: vital_sign_service,
+                authorization_engine,
+                audit_service,
+            },
+        }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_vital_sign, "Record a vital sign")
+            .get("/", Self::search_vital_signs, "Search vital signs with query parameters")
+            .get("/:id", Self::get_vital_sign, "Get vital sign by ID")
+            .put("/:id", Self::update_vital_sign, "Correct a vital sign")
+            .delete("/:id", Self::delete_vital_sign, "Delete vital sign")
+            .with_state(self.state.clone())
+    }
+
+    /// Routes under a patient, merged into the patient routes
+    pub fn trend_routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .get("/:id/vital-signs/trends", Self::trends, "Get a patient's vital sign trends")
+            .with_state(self.state.clone())
+    }
+
+    /// Record a vital sign
+    pub async fn create_vital_sign(
+        State(state): State<VitalSignState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Json(request): Json<VitalSignRequest>,
+    ) -> Result<(StatusCode, Versioned<VitalSignResponse>), ErrorReply> {
+        tracing::info!("Recording {} for patient {}", request.kind.as_str(), request.patient_id);
+
+        let context = "Failed to record vital sign";
+        Self::authorize(&state, &auth, &headers, Action::Update, request.patient_id, context).await?;
+        match state.service.create(request).await {
+            Ok(sign) => {
+                Self::audit(&state, &auth, (AuditEventType::Create, AuditAction::Create), &sign, context).await?;
+                Ok((StatusCode::CREATED, versioned(&sign.meta.clone(), Self::vital_sign_to_response(sign))))
+            }
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Get vital sign by ID
+    pub async fn get_vital_sign(
+        State(state): State<VitalSignState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Versioned<VitalSignResponse>, ErrorReply> {
+        let context = "Failed to get vital sign";
+        let sign = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Read, sign.patient_id, context).await?;
+        Self::audit(&state, &auth, (AuditEventType::Access, AuditAction::Read), &sign, context).await?;
+        Ok(versioned(&sign.meta.clone(), Self::vital_sign_to_response(sign)))
+    }
+
+    /// Search vital signs with query parameters. Signs of patients the user
+    /// may not read are left out of the page.
+    pub async fn search_vital_signs(
+        State(state): State<VitalSignState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        OriginalUri(uri): OriginalUri,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<Json<VitalSignBundle>, ErrorReply> {
+        tracing::info!("Searching vital signs with params: {:?}", params);
+
+        let context = "Failed to search vital signs";
+        let search = VitalSignSearch::from_params(&params)
+            .map_err(|e| Self::error_response("Invalid search parameters", e))?;
+        let mut result = state.service.search(&search).await.map_err(|e| Self::error_response(context, e))?;
+
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), &auth, &headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        let found = result.vital_signs.len();
+        result.vital_signs = authorizer
+            .retain(Action::Read, result.vital_signs, |sign| Resource::Patient(sign.patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        if result.vital_signs.len() < found {
+            tracing::info!("Withheld {} of {} vital signs from user {}", found - result.vital_signs.len(), found, auth.user_id);
+        }
+        for sign in &result.vital_signs {
+            Self::audit(&state, &auth, (AuditEventType::Access, AuditAction::Read), sign, context).await?;
+        }
+
+        let link = page_links(uri.path(), &params, result.next.as_ref(), result.previous.as_ref());
+        Ok(Json(Self::vital_signs_to_bundle(result, link)))
+    }
+
+    /// Correct a vital sign; `If-Match` must name the version being replaced
+    pub async fn update_vital_sign(
+        State(state): State<VitalSignState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(request): Json<VitalSignRequest>,
+    ) -> Result<Versioned<VitalSignResponse>, ErrorReply> {
+        tracing::info!("Updating vital sign: {}", id);
+
+        let expected_version = if_match_version(&headers).ok_or_else(|| {
+            (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(ErrorResponse {
+                    error: "If-Match required".to_string(),
+                    message: "Send the vital sign's ETag in If-Match to update it".to_string(),
+                }),
+            )
+        })?;
+
+        let context = "Failed to update vital sign";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.update(id, request, &expected_version).await {
+            Ok(Some(sign)) => {
+                Self::audit(&state, &auth, (AuditEventType::Update, AuditAction::Update), &sign, context).await?;
+                Ok(versioned(&sign.meta.clone(), Self::vital_sign_to_response(sign)))
+            }
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Delete vital sign
+    pub async fn delete_vital_sign(
+        State(state): State<VitalSignState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, ErrorReply> {
+        tracing::info!("Deleting vital sign: {}", id);
+
+        let context = "Failed to delete vital sign";
+        let current = Self::existing(&state, id, context).await?;
+        Self::authorize(&state, &auth, &headers, Action::Update, current.patient_id, context).await?;
+        match state.service.delete(id).await {
+            Ok(true) => {
+                Self::audit(&state, &auth, (AuditEventType::Delete, AuditAction::Delete), &current, context).await?;
+                Ok(StatusCode::NO_CONTENT)
+            }
+            Ok(false) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Time-bucketed series of the patient's vital signs, for charting
+    pub async fn trends(
+        State(state): State<VitalSignState>,
+        auth: AuthContext,
+        headers: HeaderMap,
+        Path(patient_id): Path<Uuid>,
+        Query(params): Query<Vec<(String, String)>>,
+    ) -> Result<Json<VitalSignTrends>, ErrorReply> {
+        let context = "Failed to get vital sign trends";
+        let query = TrendQuery::from_params(&params).map_err(|e| Self::error_response("Invalid trend parameters", e))?;
+        Self::authorize(&state, &auth, &headers, Action::Read, patient_id, context).await?;
+        let trends = state.service.trends(patient_id, &query).await.map_err(|e| Self::error_response(context, e))?;
+
+        let audit_log = auth
+            .audit(AuditLog::new(AuditEventType::Access, AuditAction::Read, "Observation".to_string()))
+            .with_patient(patient_id)
+            .with_details("Vital sign trends".to_string());
+        state
+            .audit_service
+            .create_audit_log(&audit_log)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        Ok(Json(trends))
+    }
+
+    /// The vital sign, or 404
+    async fn existing(state: &VitalSignState, id: Uuid, context: &str) -> Result<VitalSign, ErrorReply> {
+        match state.service.get(id).await {
+            Ok(Some(sign)) => Ok(sign),
+            Ok(None) => Err(Self::not_found(id)),
+            Err(e) => Err(Self::error_response(context, e)),
+        }
+    }
+
+    /// Fail with 403 unless the user may take `action` on the patient
+    async fn authorize(
+        state: &VitalSignState,
+        auth: &AuthContext,
+        headers: &HeaderMap,
+        action: Action,
+        patient_id: Uuid,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let authorizer = FieldAuthorizer::for_headers(state.authorization_engine.clone(), auth, headers)
+            .await
+            .map_err(|e| Self::error_response(context, e))?;
+        authorizer
+            .authorize(action, Resource::Patient(patient_id))
+            .await
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// Record the user's access to or change of a vital sign
+    async fn audit(
+        state: &VitalSignState,
+        auth: &AuthContext,
+        (event_type, action): (AuditEventType, AuditAction),
+        sign: &VitalSign,
+        context: &str,
+    ) -> Result<(), ErrorReply> {
+        let audit_log = auth
+            .audit(AuditLog::new(event_type, action, "Observation".to_string()))
+            .with_patient(sign.patient_id)
+            .with_resource(sign.id)
+            .with_details(format!("Vital sign: {}", sign.kind.as_str()));
+        state
+            .audit_service
+            .create_audit_log(&audit_log)
+            .await
+            .map(|_| ())
+            .map_err(|e| Self::error_response(context, e))
+    }
+
+    /// Convert VitalSign model to a FHIR Observation
+    pub fn vital_sign_to_response(sign: VitalSign) -> VitalSignResponse {
+        let loinc = |kind: VitalSignKind| CodeableConcept {
+            coding: vec![Coding {
+                system: Some(LOINC_SYSTEM.to_string()),
+                version: None,
+                code: Some(kind.loinc_code().to_string()),
+                display: Some(kind.display().to_string()),
+            }],
+            text: Some(kind.display().to_string()),
+        };
+
+        // A registered device is referenced; otherwise what the client knew
+        // about it is contained
+        let (contained, device) = match sign.device {
+            Some(device) => match &device.reference {
+                Some(reference) => (Vec::new(), Some(Reference { reference: reference.clone(), display: device.model.clone() })),
+                None => {
+                    let mut contained = json!({ "resourceType": "Device", "id": "device" });
+                    if let Some(manufacturer) = &device.manufacturer {
+                        contained["manufacturer"] = json!(manufacturer);
+                    }
+                    if let Some(model) = &device.model {
+                        contained["modelNumber"] = json!(model);
+                    }
+                    if let Some(serial_number) = &device.serial_number {
+                        contained["serialNumber"] = json!(serial_number);
+                    }
+                    if let Some(udi) = &device.udi {
+                        contained["udiCarrier"] = json!([{ "carrierHRF": udi }]);
+                    }
+                    (vec![contained], Some(Reference { reference: "#device".to_string(), display: device.model.clone() }))
+                }
+            },
+            None => (Vec::new(), None),
+        };
+
+        VitalSignResponse {
+            resource_type: OBSERVATION_RESOURCE_TYPE.to_string(),
+            id: sign.id,
+            meta: sign.meta,
+            contained,
+            status: sign.status,
+            category: vec![CodeableConcept {
+                coding: vec![Coding {
+                    system: Some(OBSERVATION_CATEGORY_SYSTEM.to_string()),
+                    version: None,
+                    code: Some("vital-signs".to_string()),
+                    display: Some("Vital Signs".to_string()),
+                }],
+                text: None,
+            }],
+            code: loinc(sign.kind),
+            subject: Reference {
+                reference: format!("Patient/{}", sign.patient_id),
+                display: None,
+            },
+            encounter: sign.encounter_id.map(|id| Reference {
+                reference: format!("Encounter/{}", id),
+                display: None,
+            }),
+            effective_date_time: sign.effective,
+            issued: sign.issued,
+            performer: sign.performer.into_iter().collect(),
+            value_quantity: sign.value,
+            note: sign.note.into_iter().map(|text| json!({ "text": text })).collect(),
+            device,
+            component: sign
+                .component
+                .into_iter()
+                .map(|component| VitalSignComponentResponse {
+                    code: loinc(component.kind),
+                    value_quantity: component.value,
+                })
+                .collect(),
+        }
+    }
+
+    /// Convert a page of search results to a FHIR searchset Bundle
+    fn vital_signs_to_bundle(result: VitalSignSearchResult, link: Vec<BundleLink>) -> VitalSignBundle {
+        let entry = result
+            .vital_signs
+            .into_iter()
+            .map(|sign| VitalSignBundleEntry {
+                full_url: format!("Observation/{}", sign.id),
+                resource: Self::vital_sign_to_response(sign),
+                search: EntrySearch {
+                    mode: SearchEntryMode::Match,
+                },
+            })
+            .collect();
+
+        VitalSignBundle {
+            resource_type: "Bundle".to_string(),
+            id: Uuid::new_v4(),
+            meta: ResourceMeta {
+                version_id: Some("1".to_string()),
+                last_updated: Utc::now(),
+                profile: vec!["http://hl7.org/fhir/StructureDefinition/Bundle".to_string()],
+                security: vec![],
+                tag: vec![],
+            },
+            bundle_type: "searchset".to_string(),
+            total: result.total,
+            link,
+            entry,
+        }
+    }
+
+    fn not_found(id: Uuid) -> ErrorReply {
+        tracing::warn!("Vital sign not found: {}", id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Vital sign not found".to_string(),
+                message: format!("Vital sign with id {} not found", id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> ErrorReply {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => precondition_status(&e).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        tracing::error!("{}: {}", context, e);
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
@@ -0,0 +1,555 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{DeviceSource, ObservationStatus, Quantity, Reference, VitalSign, VitalSignComponent, VitalSignKind};
+use crate::modules::events::{DomainEvent, EventBus};
+use crate::modules::vital_sign::vital_sign_trends::{TrendQuery, VitalSignTrends};
+use crate::modules::vital_sign::vital_sign_units::{check_quantity, ucum};
+use crate::utils::etag::next_version_id;
+use crate::utils::fhir_search::{reference_id, split_modifier, DateParam, SearchParamDefinition, TokenParam, TotalMode};
+use crate::utils::pagination::{count_matches, Cursor, PageRequest, PagingPolicy, RowKey, SortKey};
+
+// Import SQL queries from separate file
+use crate::modules::vital_sign::vital_sign_sql::*;
+
+/// How far ahead of the server clock a measurement may be dated, for
+/// devices whose clocks run fast
+const CLOCK_SKEW_MINUTES: i64 = 5;
+
+/// Vital sign from a row of any query selecting its columns
+fn vital_sign_from_row(row: &PgRow) -> VitalSign {
+    let value: Option<f64> = row.get("value");
+    let unit: Option<String> = row.get("unit");
+    VitalSign {
+        id: row.get("id"),
+        patient_id: row.get("patient_id"),
+        encounter_id: row.get("encounter_id"),
+        status: ObservationStatus::from_string(row.get("status")),
+        kind: VitalSignKind::from_string(row.get("kind")).unwrap_or(VitalSignKind::HeartRate),
+        value: value.zip(unit).map(|(value, unit)| ucum(value, &unit)),
+        component: serde_json::from_value(row.get("component")).unwrap_or_default(),
+        effective: row.get("effective_date_time"),
+        issued: row.get("issued"),
+        device: row
+            .get::<Option<serde_json::Value>, _>("device")
+            .and_then(|device| serde_json::from_value(device).ok()),
+        performer: row
+            .get::<Option<serde_json::Value>, _>("performer")
+            .and_then(|performer| serde_json::from_value(performer).ok()),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        meta: serde_json::from_value(row.get("meta")).unwrap_or_default(),
+    }
+}
+
+/// Check a measurement before it is saved: blood pressure has exactly its
+/// systolic and diastolic components, systolic the higher; every other
+/// sign has a value and no components. Every quantity is in a UCUM unit
+/// its sign accepts, with a plausible value, and the measurement is not
+/// dated in the future.
+pub fn check_vital_sign(sign: &VitalSign) -> Result<(), HimsError> {
+    let invalid = |message: String| HimsError::ValidationError { message };
+
+    if sign.kind.is_component() {
+        return Err(invalid(format!(
+            "{} is recorded as a component of blood pressure",
+            sign.kind.display()
+        )));
+    }
+    if sign.effective > Utc::now() + Duration::minutes(CLOCK_SKEW_MINUTES) {
+        return Err(invalid("Vital signs cannot be taken in the future".to_string()));
+    }
+
+    if sign.kind == VitalSignKind::BloodPressure {
+        if sign.value.is_some() {
+            return Err(invalid("Blood pressure is recorded as systolic and diastolic components".to_string()));
+        }
+        let mut kinds: Vec<VitalSignKind> = sign.component.iter().map(|component| component.kind).collect();
+        kinds.sort();
+        if kinds != [VitalSignKind::SystolicBloodPressure, VitalSignKind::DiastolicBloodPressure] {
+            return Err(invalid(
+                "Blood pressure needs one systolic and one diastolic component".to_string(),
+            ));
+        }
+        for component in &sign.component {
+            check_quantity(component.kind, &component.value)?;
+        }
+        let systolic = sign.component(VitalSignKind::SystolicBloodPressure).map(|value| value.value);
+        let diastolic = sign.component(VitalSignKind::DiastolicBloodPressure).map(|value| value.value);
+        if systolic < diastolic {
+            return Err(invalid("Systolic pressure is below diastolic pressure".to_string()));
+        }
+        return Ok(());
+    }
+
+    if !sign.component.is_empty() {
+        return Err(invalid(format!("{} has no components", sign.kind.display())));
+    }
+    let value = sign
+        .value
+        .as_ref()
+        .ok_or_else(|| invalid(format!("{} needs a value", sign.kind.display())))?;
+    check_quantity(sign.kind, value)?;
+    Ok(())
+}
+
+/// A measurement as clients send it to record or replace one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VitalSignRequest {
+    pub patient_id: Uuid,
+    pub encounter_id: Option<Uuid>,
+    #[serde(default)]
+    pub status: ObservationStatus,
+    pub kind: VitalSignKind,
+    pub value: Option<Quantity>,
+    #[serde(default)]
+    pub component: Vec<VitalSignComponent>,
+    /// When the measurement was taken; now if not given
+    pub effective: Option<DateTime<Utc>>,
+    pub device: Option<DeviceSource>,
+    pub performer: Option<Reference>,
+    pub note: Option<String>,
+}
+
+impl From<VitalSignRequest> for VitalSign {
+    fn from(request: VitalSignRequest) -> Self {
+        let mut sign = VitalSign::new(request.patient_id, request.kind, request.effective.unwrap_or_else(Utc::now));
+        sign.encounter_id = request.encounter_id;
+        sign.status = request.status;
+        sign.value = request.value;
+        sign.component = request.component;
+        sign.device = request.device;
+        sign.performer = request.performer;
+        sign.note = request.note;
+        sign
+    }
+}
+
+/// Page size of a vital sign search unless `_count` says otherwise
+pub const DEFAULT_SEARCH_COUNT: i64 = 50;
+
+/// Largest page a vital sign search returns
+pub const MAX_SEARCH_COUNT: i64 = 500;
+
+/// Paging of vital sign searches
+pub const SEARCH_PAGING: PagingPolicy = PagingPolicy::new(DEFAULT_SEARCH_COUNT, MAX_SEARCH_COUNT, TotalMode::Accurate);
+
+/// Order of vital sign searches, most recently taken first, as
+/// SEARCH_VITAL_SIGNS keys its rows
+const SEARCH_KEYS: [SortKey; 1] = [SortKey::new("effective_date_time", "timestamptz", true)];
+
+/// Parsed vital sign search
+#[derive(Debug, Clone, PartialEq)]
+pub struct VitalSignSearch {
+    pub patient: Vec<Uuid>,
+    pub encounter: Vec<Uuid>,
+    /// Kinds, from `code` or `kind`; any matches
+    pub kind: Vec<String>,
+    /// Status codes; any matches
+    pub status: Vec<String>,
+    pub date: Vec<DateParam>,
+    /// `_count`, `_cursor` and `_total`
+    pub page: PageRequest,
+}
+
+impl Default for VitalSignSearch {
+    fn default() -> Self {
+        Self {
+            patient: Vec::new(),
+            encounter: Vec::new(),
+            kind: Vec::new(),
+            status: Vec::new(),
+            date: Vec::new(),
+            page: PageRequest::new(SEARCH_PAGING),
+        }
+    }
+}
+
+/// A page of vital sign search results
+#[derive(Debug)]
+pub struct VitalSignSearchResult {
+    pub vital_signs: Vec<VitalSign>,
+    /// All matches across pages, unless `_total=none`
+    pub total: Option<i64>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+impl VitalSignSearch {
+    /// Search parameters `from_params` supports, for the CapabilityStatement
+    pub const SEARCH_PARAMS: &'static [SearchParamDefinition] = &[
+        SearchParamDefinition {
+            name: "patient",
+            param_type: "reference",
+            documentation: "The patient measured",
+        },
+        SearchParamDefinition {
+            name: "encounter",
+            param_type: "reference",
+            documentation: "The encounter the measurement was taken in",
+        },
+        SearchParamDefinition {
+            name: "code",
+            param_type: "token",
+            documentation: "LOINC code of the sign; systolic and diastolic codes match blood pressure",
+        },
+        SearchParamDefinition {
+            name: "status",
+            param_type: "token",
+            documentation: "Observation status; comma-separated values match any",
+        },
+        SearchParamDefinition {
+            name: "date",
+            param_type: "date",
+            documentation: "When the measurement was taken; supports eq, ne, gt, lt, ge and le prefixes",
+        },
+    ];
+
+    /// Parse query parameters. Unknown parameters are ignored; invalid
+    /// values of supported ones are errors.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let mut search = Self::default();
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+
+        for (param, value) in params {
+            let (name, modifier) = split_modifier(param);
+            match (name, modifier) {
+                ("patient" | "subject", None | Some("Patient")) => {
+                    for reference in value.split(',') {
+                        search.patient.push(reference_id(reference, "Patient")?);
+                    }
+                }
+                ("encounter", None | Some("Encounter")) => {
+                    for reference in value.split(',') {
+                        search.encounter.push(reference_id(reference, "Encounter")?);
+                    }
+                }
+                ("code", None) => {
+                    for token in value.split(',') {
+                        let token = TokenParam::parse(token)?;
+                        if token.system.as_deref().is_some_and(|system| system != crate::models::constants::LOINC_SYSTEM) {
+                            return Err(invalid(name, value));
+                        }
+                        let kind = token
+                            .code
+                            .as_deref()
+                            .and_then(VitalSignKind::from_loinc_code)
+                            .ok_or_else(|| invalid(name, value))?;
+                        let kind = if kind.is_component() { VitalSignKind::BloodPressure } else { kind };
+                        search.kind.push(kind.as_str().to_string());
+                    }
+                }
+                ("status", None) => {
+                    for status in value.split(',') {
+                        serde_json::from_value::<ObservationStatus>(serde_json::Value::String(status.to_string()))
+                            .map_err(|_| invalid(name, status))?;
+                        search.status.push(status.to_string());
+                    }
+                }
+                ("date", None) => search.date.push(DateParam::parse(value)?),
+                ("category", None) if value == "vital-signs" || value.ends_with("|vital-signs") => {}
+                _ if search.page.apply(name, value)? => {}
+                _ => tracing::debug!("Ignoring unsupported vital sign search parameter {}", param),
+            }
+        }
+        search.page.check_cursor(&SEARCH_KEYS)?;
+        Ok(search)
+    }
+
+    /// Append `AND` conditions on vital signs for these criteria
+    pub(crate) fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if !self.patient.is_empty() {
+            query.push(" AND patient_id = ANY(").push_bind(self.patient.clone()).push(")");
+        }
+        if !self.encounter.is_empty() {
+            query.push(" AND encounter_id = ANY(").push_bind(self.encounter.clone()).push(")");
+        }
+        if !self.kind.is_empty() {
+            query.push(" AND kind = ANY(").push_bind(self.kind.clone()).push(")");
+        }
+        if !self.status.is_empty() {
+            query.push(" AND status = ANY(").push_bind(self.status.clone()).push(")");
+        }
+        for date in &self.date {
+            date.push_condition(query, "effective_date_time");
+        }
+    }
+}
+
+/// Vital sign service for capturing measurements and charting their trends
+#[derive(Debug, Clone)]
+pub struct VitalSignService {
+    pool: PgPool,
+    events: EventBus,
+}
+
+impl VitalSignService {
+    /// Create new vital sign service
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_events(pool, EventBus::default())
+    }
+
+    /// Create the service publishing to a shared event bus
+    pub fn with_events(pool: PgPool, events: EventBus) -> Self {
+        Self { pool, events }
+    }
+
+    /// Record a measurement
+    pub async fn create(&self, request: VitalSignRequest) -> Result<VitalSign, HimsError> {
+        let sign: VitalSign = request.into();
+        check_vital_sign(&sign)?;
+
+        sqlx::query(INSERT_VITAL_SIGN)
+            .bind(sign.id)
+            .bind(sign.patient_id)
+            .bind(sign.encounter_id)
+            .bind(sign.status.as_str())
+            .bind(sign.kind.as_str())
+            .bind(sign.kind.loinc_code())
+            .bind(sign.value.as_ref().map(|value| value.value))
+            .bind(sign.value.as_ref().and_then(|value| value.code.clone()))
+            .bind(serde_json::to_value(&sign.component).unwrap_or_default())
+            .bind(sign.effective)
+            .bind(sign.issued)
+            .bind(sign.device.as_ref().and_then(|device| serde_json::to_value(device).ok()))
+            .bind(sign.performer.as_ref().and_then(|performer| serde_json::to_value(performer).ok()))
+            .bind(&sign.note)
+            .bind(sign.created_at)
+            .bind(sign.updated_at)
+            .bind(serde_json::to_value(&sign.meta).unwrap_or_default())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        self.events.publish(DomainEvent::VitalsRecorded {
+            observation_id: sign.id,
+            patient_id: sign.patient_id,
+        });
+        Ok(sign)
+    }
+
+    /// Get vital sign by ID
+    pub async fn get(&self, id: Uuid) -> Result<Option<VitalSign>, HimsError> {
+        let row = sqlx::query(GET_VITAL_SIGN_BY_ID)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        Ok(row.as_ref().map(vital_sign_from_row))
+    }
+
+    /// Search vital signs, a page at a time
+    pub async fn search(&self, search: &VitalSignSearch) -> Result<VitalSignSearchResult, HimsError> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_VITAL_SIGNS);
+        search.push_filters(&mut query);
+        search.page.push_cursor(&mut query, &SEARCH_KEYS);
+        search.page.push_order(&mut query, &SEARCH_KEYS);
+        query.push(" LIMIT ").push_bind(search.page.fetch_limit());
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        let mut fetched = Vec::new();
+        for row in rows {
+            let sign = vital_sign_from_row(&row);
+            fetched.push((sign, RowKey::from_column(row.get("page_key"))?));
+        }
+        let page = search.page.page(fetched);
+
+        let total = count_matches(
+            &self.pool,
+            search.page.total(),
+            COUNT_VITAL_SIGNS,
+            ESTIMATE_VITAL_SIGNS,
+            |query| search.push_filters(query),
+        )
+        .await
+        .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(VitalSignSearchResult {
+            vital_signs: page.items,
+            total,
+            next: page.next,
+            previous: page.previous,
+        })
+    }
+
+    /// Correct a measurement if it is still at `expected_version`, or
+    /// `None` if there is no such measurement. The patient and kind stay
+    /// the same; a correction to a final measurement is marked amended.
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: VitalSignRequest,
+        expected_version: &str,
+    ) -> Result<Option<VitalSign>, HimsError> {
+        let Some(current) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let current_version = current.meta.version_id.as_deref().unwrap_or("1");
+        if current_version != expected_version {
+            return Err(HimsError::PreconditionFailed {
+                message: format!("Vital sign {} is at version {}, not {}", id, current_version, expected_version),
+            });
+        }
+        if request.patient_id != current.patient_id || request.kind != current.kind {
+            return Err(HimsError::ValidationError {
+                message: format!(
+                    "Vital sign {} is a {} of patient {}",
+                    id,
+                    current.kind.as_str(),
+                    current.patient_id
+                ),
+            });
+        }
+
+        let mut sign: VitalSign = request.into();
+        check_vital_sign(&sign)?;
+        if current.status == ObservationStatus::Final && sign.status == ObservationStatus::Final {
+            sign.status = ObservationStatus::Amended;
+        }
+        let rows_affected = sqlx::query(UPDATE_VITAL_SIGN)
+            .bind(id)
+            .bind(sign.encounter_id)
+            .bind(sign.status.as_str())
+            .bind(sign.value.as_ref().map(|value| value.value))
+            .bind(sign.value.as_ref().and_then(|value| value.code.clone()))
+            .bind(serde_json::to_value(&sign.component).unwrap_or_default())
+            .bind(sign.effective)
+            .bind(sign.device.as_ref().and_then(|device| serde_json::to_value(device).ok()))
+            .bind(sign.performer.as_ref().and_then(|performer| serde_json::to_value(performer).ok()))
+            .bind(&sign.note)
+            .bind(sign.issued)
+            .bind(next_version_id(Some(expected_version)))
+            .bind(expected_version)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(HimsError::ConflictError {
+                message: format!("Vital sign {} was modified concurrently", id),
+            });
+        }
+        self.events.publish(DomainEvent::VitalsUpdated { observation_id: id });
+
+        self.get(id).await
+    }
+
+    /// Soft delete vital sign
+    pub async fn delete(&self, id: Uuid) -> Result<bool, HimsError> {
+        let rows_affected = sqlx::query(SOFT_DELETE_VITAL_SIGN)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?
+            .rows_affected();
+        if rows_affected > 0 {
+            self.events.publish(DomainEvent::VitalsDeleted { observation_id: id });
+        }
+        Ok(rows_affected > 0)
+    }
+
+    /// The patient's measurements over the query's range, bucketed per sign
+    pub async fn trends(&self, patient_id: Uuid, query: &TrendQuery) -> Result<VitalSignTrends, HimsError> {
+        let rows = sqlx::query(GET_VITAL_SIGNS_FOR_TRENDS)
+            .bind(patient_id)
+            .bind(query.stored_kinds())
+            .bind(query.from)
+            .bind(query.to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+        let signs: Vec<VitalSign> = rows.iter().map(vital_sign_from_row).collect();
+        Ok(VitalSignTrends::new(patient_id, query, &signs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn blood_pressure(systolic: f64, diastolic: f64) -> VitalSign {
+        let mut sign = VitalSign::new(Uuid::new_v4(), VitalSignKind::BloodPressure, Utc::now());
+        sign.component = vec![
+            VitalSignComponent {
+                kind: VitalSignKind::SystolicBloodPressure,
+                value: ucum(systolic, "mm[Hg]"),
+            },
+            VitalSignComponent {
+                kind: VitalSignKind::DiastolicBloodPressure,
+                value: ucum(diastolic, "mm[Hg]"),
+            },
+        ];
+        sign
+    }
+
+    #[test]
+    fn test_check_vital_sign() {
+        assert!(check_vital_sign(&blood_pressure(120.0, 80.0)).is_ok());
+        assert!(check_vital_sign(&blood_pressure(80.0, 120.0)).is_err(), "systolic below diastolic");
+        let mut sign = blood_pressure(120.0, 80.0);
+        sign.component.pop();
+        assert!(check_vital_sign(&sign).is_err(), "missing diastolic");
+        sign.value = Some(ucum(120.0, "mm[Hg]"));
+        assert!(check_vital_sign(&sign).is_err(), "blood pressure value");
+
+        let mut sign = VitalSign::new(Uuid::new_v4(), VitalSignKind::HeartRate, Utc::now());
+        assert!(check_vital_sign(&sign).is_err(), "no value");
+        sign.value = Some(ucum(72.0, "/min"));
+        assert!(check_vital_sign(&sign).is_ok());
+        assert!(sign.meta.profile.contains(&"http://hl7.org/fhir/StructureDefinition/heartrate".to_string()));
+        sign.effective = Utc::now() + Duration::hours(1);
+        assert!(check_vital_sign(&sign).is_err(), "taken in the future");
+
+        let mut sign = VitalSign::new(Uuid::new_v4(), VitalSignKind::SystolicBloodPressure, Utc::now());
+        sign.value = Some(ucum(120.0, "mm[Hg]"));
+        assert!(check_vital_sign(&sign).is_err(), "component on its own");
+    }
+
+    #[test]
+    fn test_search_from_params() {
+        let search = VitalSignSearch::from_params(&params(&[
+            ("code", "http://loinc.org|8867-4,8480-6"),
+            ("status", "final,amended"),
+            ("category", "vital-signs"),
+            ("date", "ge2024-01"),
+        ]))
+        .unwrap();
+        assert_eq!(search.kind, vec!["heart-rate".to_string(), "blood-pressure".to_string()]);
+        assert_eq!(search.status.len(), 2);
+
+        assert!(VitalSignSearch::from_params(&params(&[("code", "http://snomed.info/sct|8867-4")])).is_err());
+        assert!(VitalSignSearch::from_params(&params(&[("code", "2339-0")])).is_err());
+        assert!(VitalSignSearch::from_params(&params(&[("status", "done")])).is_err());
+    }
+
+    #[test]
+    fn test_advertised_params_are_supported() {
+        let id = Uuid::new_v4();
+        for param in VitalSignSearch::SEARCH_PARAMS {
+            let value = match param.name {
+                "code" => "8867-4".to_string(),
+                "status" => "final".to_string(),
+                "date" => "2024-01".to_string(),
+                _ => id.to_string(),
+            };
+            let search = VitalSignSearch::from_params(&params(&[(param.name, value.as_str())])).unwrap();
+            assert_ne!(search, VitalSignSearch::default(), "{} was ignored", param.name);
+        }
+    }
+}
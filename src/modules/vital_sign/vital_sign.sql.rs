@@ -0,0 +1,75 @@
+//! Vital Sign SQL Queries
+//! 
+//! This file contains all SQL queries used by the vital sign service.
+
+/// Insert a new vital sign
+pub const INSERT_VITAL_SIGN: &str = r#"
+    INSERT INTO vital_signs (
+        id, patient_id, encounter_id, status, kind, loinc_code, value, unit, component,
+        effective_date_time, issued, device, performer, note, created_at, updated_at, meta
+    ) VALUES (
+        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17
+    )
+"#;
+
+/// Get vital sign by ID
+pub const GET_VITAL_SIGN_BY_ID: &str = r#"
+    SELECT id, patient_id, encounter_id, status, kind, value, unit, component,
+           effective_date_time, issued, device, performer, note, created_at, updated_at, meta
+    FROM vital_signs
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// Replace a vital sign's measurement if it is still at version $13, moving
+/// it to version $12. The patient and kind of a measurement never change.
+pub const UPDATE_VITAL_SIGN: &str = r#"
+    UPDATE vital_signs
+    SET encounter_id = $2, status = $3, value = $4, unit = $5, component = $6,
+        effective_date_time = $7, device = $8, performer = $9, note = $10, issued = $11,
+        updated_at = NOW(),
+        meta = jsonb_set(jsonb_set(meta, '{last_updated}', to_jsonb(NOW())), '{version_id}', to_jsonb($12::text))
+    WHERE id = $1 AND deleted_at IS NULL AND COALESCE(meta->>'version_id', '1') = $13
+"#;
+
+/// Soft delete vital sign
+pub const SOFT_DELETE_VITAL_SIGN: &str = r#"
+    UPDATE vital_signs
+    SET deleted_at = NOW(), updated_at = NOW(), meta = jsonb_set(
+            jsonb_set(meta, '{last_updated}', to_jsonb(NOW())),
+            '{version_id}', to_jsonb((COALESCE(meta->>'version_id', '1')::bigint + 1)::text))
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// A patient's measurements of kinds $2 taken in [$3, $4), oldest first.
+/// Cancelled and entered-in-error measurements are not charted.
+pub const GET_VITAL_SIGNS_FOR_TRENDS: &str = r#"
+    SELECT id, patient_id, encounter_id, status, kind, value, unit, component,
+           effective_date_time, issued, device, performer, note, created_at, updated_at, meta
+    FROM vital_signs
+    WHERE patient_id = $1 AND deleted_at IS NULL
+      AND kind = ANY($2)
+      AND effective_date_time >= $3 AND effective_date_time < $4
+      AND status NOT IN ('cancelled', 'entered-in-error')
+    ORDER BY effective_date_time, id
+"#;
+
+/// Start of a vital sign search, most recently taken first; the service
+/// appends the filters and the page's cursor, order and limit
+pub const SEARCH_VITAL_SIGNS: &str = r#"
+    SELECT id, patient_id, encounter_id, status, kind, value, unit, component,
+           effective_date_time, issued, device, performer, note, created_at, updated_at, meta,
+           ARRAY[effective_date_time::text, id::text] AS page_key
+    FROM vital_signs
+    WHERE deleted_at IS NULL
+"#;
+
+/// Start of an accurate vital sign search total
+pub const COUNT_VITAL_SIGNS: &str = r#"
+    SELECT COUNT(*) FROM vital_signs WHERE deleted_at IS NULL
+"#;
+
+/// Query plan of a vital sign search, whose row estimate answers
+/// `_total=estimate`
+pub const ESTIMATE_VITAL_SIGNS: &str = r#"
+    EXPLAIN (FORMAT JSON) SELECT 1 FROM vital_signs WHERE deleted_at IS NULL
+"#;
@@ -0,0 +1,342 @@
+//! Vital sign trends
+//!
+//! A patient's measurements over a time range, grouped into series per
+//! sign (blood pressure as its systolic and diastolic series) and summarised
+//! per time bucket, in each sign's canonical unit, for charting.
+
+use chrono::{DateTime, Datelike, Duration, Months, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::models::{VitalSign, VitalSignKind};
+use crate::modules::vital_sign::vital_sign_units::{canonical_unit, to_canonical};
+
+/// Most buckets a trend may span, so a fine bucket over a long range
+/// cannot produce an unchartable series
+pub const MAX_TREND_BUCKETS: i64 = 2000;
+
+/// Range of a trend unless `from` says otherwise
+pub const DEFAULT_TREND_DAYS: i64 = 30;
+
+/// Width of a trend bucket; buckets start on UTC boundaries, weeks on
+/// Mondays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendBucket {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl TrendBucket {
+    /// Start of the bucket holding `at`
+    pub fn start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let day = at.date_naive();
+        let start = match self {
+            TrendBucket::Hour => day.and_hms_opt(at.hour(), 0, 0),
+            TrendBucket::Day => day.and_hms_opt(0, 0, 0),
+            TrendBucket::Week => (day - Duration::days(day.weekday().num_days_from_monday() as i64)).and_hms_opt(0, 0, 0),
+            TrendBucket::Month => day.with_day(1).unwrap_or(day).and_hms_opt(0, 0, 0),
+        };
+        start.map(|start| Utc.from_utc_datetime(&start)).unwrap_or(at)
+    }
+
+    /// Start of the bucket after the one starting at `start`
+    pub fn next(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            TrendBucket::Hour => start + Duration::hours(1),
+            TrendBucket::Day => start + Duration::days(1),
+            TrendBucket::Week => start + Duration::weeks(1),
+            TrendBucket::Month => start.checked_add_months(Months::new(1)).unwrap_or(start),
+        }
+    }
+
+    /// Rough bucket width, for bounding the number of buckets
+    fn approximate(&self) -> Duration {
+        match self {
+            TrendBucket::Hour => Duration::hours(1),
+            TrendBucket::Day => Duration::days(1),
+            TrendBucket::Week => Duration::weeks(1),
+            TrendBucket::Month => Duration::days(28),
+        }
+    }
+}
+
+/// Parsed trend request
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrendQuery {
+    /// Signs to chart; all of them when empty
+    pub kinds: Vec<VitalSignKind>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket: TrendBucket,
+}
+
+impl TrendQuery {
+    /// Parse `kind` (comma-separated), `from`, `to` (RFC 3339) and `bucket`.
+    /// The range defaults to the last 30 days.
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, HimsError> {
+        let invalid = |name: &str, value: &str| HimsError::ValidationError {
+            message: format!("Invalid value for {}: {:?}", name, value),
+        };
+        let date = |name: &str, value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|_| invalid(name, value))
+        };
+
+        let mut kinds = Vec::new();
+        let (mut from, mut to, mut bucket) = (None, None, TrendBucket::default());
+        for (name, value) in params {
+            match name.as_str() {
+                "kind" => {
+                    for kind in value.split(',') {
+                        let kind = VitalSignKind::from_string(kind).ok_or_else(|| invalid(name, kind))?;
+                        if !kinds.contains(&kind) {
+                            kinds.push(kind);
+                        }
+                    }
+                }
+                "from" => from = Some(date(name, value)?),
+                "to" => to = Some(date(name, value)?),
+                "bucket" => {
+                    bucket = serde_json::from_value(serde_json::Value::String(value.clone()))
+                        .map_err(|_| invalid(name, value))?
+                }
+                _ => tracing::debug!("Ignoring unsupported trend parameter {}", name),
+            }
+        }
+
+        let to = to.unwrap_or_else(Utc::now);
+        let from = from.unwrap_or(to - Duration::days(DEFAULT_TREND_DAYS));
+        if from >= to {
+            return Err(HimsError::ValidationError {
+                message: "from must be before to".to_string(),
+            });
+        }
+        if (to - from).num_seconds() / bucket.approximate().num_seconds() > MAX_TREND_BUCKETS {
+            return Err(HimsError::ValidationError {
+                message: format!("The range spans more than {} buckets; use a wider bucket", MAX_TREND_BUCKETS),
+            });
+        }
+        Ok(Self { kinds, from, to, bucket })
+    }
+
+    /// Kinds stored as rows that this query reads
+    pub fn stored_kinds(&self) -> Vec<String> {
+        let kinds: Vec<VitalSignKind> = if self.kinds.is_empty() {
+            VitalSignKind::ALL.to_vec()
+        } else {
+            self.kinds.clone()
+        };
+        let mut stored: Vec<String> = kinds
+            .into_iter()
+            .map(|kind| if kind.is_component() { VitalSignKind::BloodPressure } else { kind })
+            .map(|kind| kind.as_str().to_string())
+            .collect();
+        stored.sort();
+        stored.dedup();
+        stored
+    }
+
+    /// Whether the series of `kind` was asked for; blood pressure is charted
+    /// as its components
+    fn wants(&self, kind: VitalSignKind) -> bool {
+        self.kinds.is_empty()
+            || self.kinds.contains(&kind)
+            || (kind.is_component() && self.kinds.contains(&VitalSignKind::BloodPressure))
+    }
+}
+
+/// Summary of the measurements in one bucket
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrendPoint {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// The bucket's latest measurement
+    pub last: f64,
+}
+
+/// Buckets of one sign, oldest first; buckets without measurements are
+/// left out
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrendSeries {
+    pub kind: VitalSignKind,
+    /// LOINC code of the sign
+    pub code: &'static str,
+    pub display: &'static str,
+    /// UCUM unit of every value in the series
+    pub unit: &'static str,
+    pub points: Vec<TrendPoint>,
+}
+
+/// A patient's vital sign trends
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VitalSignTrends {
+    pub patient_id: Uuid,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket: TrendBucket,
+    pub series: Vec<TrendSeries>,
+}
+
+impl VitalSignTrends {
+    /// Trends of `signs`, which must be the patient's measurements in the
+    /// query's range, in order of when they were taken
+    pub fn new(patient_id: Uuid, query: &TrendQuery, signs: &[VitalSign]) -> Self {
+        // Per kind, per bucket start: the values in the bucket in order
+        let mut values: BTreeMap<VitalSignKind, BTreeMap<DateTime<Utc>, Vec<f64>>> = BTreeMap::new();
+        for sign in signs {
+            let measurements = sign
+                .value
+                .iter()
+                .map(|value| (sign.kind, value))
+                .chain(sign.component.iter().map(|component| (component.kind, &component.value)));
+            for (kind, quantity) in measurements {
+                if !query.wants(kind) {
+                    continue;
+                }
+                if let Some(value) = to_canonical(kind, quantity) {
+                    values
+                        .entry(kind)
+                        .or_default()
+                        .entry(query.bucket.start(sign.effective))
+                        .or_default()
+                        .push(value);
+                }
+            }
+        }
+
+        let series = values
+            .into_iter()
+            .map(|(kind, buckets)| TrendSeries {
+                kind,
+                code: kind.loinc_code(),
+                display: kind.display(),
+                unit: canonical_unit(kind),
+                points: buckets
+                    .into_iter()
+                    .map(|(start, values)| TrendPoint {
+                        start,
+                        end: query.bucket.next(start),
+                        count: values.len(),
+                        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                        mean: values.iter().sum::<f64>() / values.len() as f64,
+                        last: values[values.len() - 1],
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            patient_id,
+            from: query.from,
+            to: query.to,
+            bucket: query.bucket,
+            series,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::VitalSignComponent;
+    use crate::modules::vital_sign::vital_sign_units::ucum;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_bucket_starts() {
+        let at = at("2024-03-14T15:45:10Z");
+        assert_eq!(TrendBucket::Hour.start(at), self::at("2024-03-14T15:00:00Z"));
+        assert_eq!(TrendBucket::Day.start(at), self::at("2024-03-14T00:00:00Z"));
+        assert_eq!(TrendBucket::Week.start(at), self::at("2024-03-11T00:00:00Z"));
+        assert_eq!(TrendBucket::Month.start(at), self::at("2024-03-01T00:00:00Z"));
+        assert_eq!(TrendBucket::Month.next(self::at("2024-01-01T00:00:00Z")), self::at("2024-02-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_query_from_params() {
+        let query = TrendQuery::from_params(&params(&[
+            ("kind", "blood-pressure,heart-rate"),
+            ("from", "2024-01-01T00:00:00Z"),
+            ("to", "2024-02-01T00:00:00Z"),
+            ("bucket", "week"),
+        ]))
+        .unwrap();
+        assert_eq!(query.bucket, TrendBucket::Week);
+        assert_eq!(query.stored_kinds(), vec!["blood-pressure".to_string(), "heart-rate".to_string()]);
+
+        assert!(TrendQuery::from_params(&params(&[("kind", "glucose")])).is_err());
+        assert!(TrendQuery::from_params(&params(&[("bucket", "minute")])).is_err());
+        assert!(TrendQuery::from_params(&params(&[("from", "2024-02-01T00:00:00Z"), ("to", "2024-01-01T00:00:00Z")])).is_err());
+        // A year of hourly buckets is too many
+        assert!(TrendQuery::from_params(&params(&[
+            ("from", "2023-01-01T00:00:00Z"),
+            ("to", "2024-01-01T00:00:00Z"),
+            ("bucket", "hour"),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_trends() {
+        let patient = Uuid::new_v4();
+        let mut morning = VitalSign::new(patient, VitalSignKind::BodyTemperature, at("2024-03-14T08:00:00Z"));
+        morning.value = Some(ucum(37.0, "Cel"));
+        let mut evening = VitalSign::new(patient, VitalSignKind::BodyTemperature, at("2024-03-14T20:00:00Z"));
+        evening.value = Some(ucum(100.4, "[degF]"));
+        let mut pressure = VitalSign::new(patient, VitalSignKind::BloodPressure, at("2024-03-15T09:00:00Z"));
+        pressure.component = vec![
+            VitalSignComponent {
+                kind: VitalSignKind::SystolicBloodPressure,
+                value: ucum(120.0, "mm[Hg]"),
+            },
+            VitalSignComponent {
+                kind: VitalSignKind::DiastolicBloodPressure,
+                value: ucum(80.0, "mm[Hg]"),
+            },
+        ];
+
+        let query = TrendQuery::from_params(&params(&[
+            ("from", "2024-03-01T00:00:00Z"),
+            ("to", "2024-04-01T00:00:00Z"),
+        ]))
+        .unwrap();
+        let trends = VitalSignTrends::new(patient, &query, &[morning, evening, pressure]);
+
+        let kinds: Vec<_> = trends.series.iter().map(|series| series.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                VitalSignKind::BodyTemperature,
+                VitalSignKind::SystolicBloodPressure,
+                VitalSignKind::DiastolicBloodPressure
+            ]
+        );
+        let temperature = &trends.series[0].points;
+        assert_eq!(temperature.len(), 1);
+        assert_eq!(temperature[0].count, 2);
+        assert_eq!(temperature[0].min, 37.0);
+        assert!((temperature[0].max - 38.0).abs() < 1e-9);
+        assert!((temperature[0].last - 38.0).abs() < 1e-9);
+        assert_eq!(trends.series[0].unit, "Cel");
+        assert_eq!(trends.series[1].points[0].mean, 120.0);
+    }
+}
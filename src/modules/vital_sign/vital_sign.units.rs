@@ -0,0 +1,152 @@
+//! UCUM units of vital signs
+//!
+//! Each sign has a canonical unit trends are charted in, and the UCUM
+//! units the vital-signs profiles allow, converted to it with `Ucum`.
+//! Values outside the sign's plausible range are rejected as entry errors.
+
+use std::ops::RangeInclusive;
+
+use crate::core::HimsError;
+use crate::models::constants::UCUM_SYSTEM;
+use crate::models::{Quantity, VitalSignKind};
+use crate::standards::ucum::Ucum;
+
+const PER_MINUTE: &[&str] = &["/min"];
+const PERCENT: &[&str] = &["%"];
+const CELSIUS: &[&str] = &["Cel", "[degF]"];
+const CENTIMETRES: &[&str] = &["cm", "m", "[in_i]"];
+const KILOGRAMS: &[&str] = &["kg", "g", "[lb_av]"];
+const KILOGRAMS_PER_SQUARE_METRE: &[&str] = &["kg/m2"];
+const MILLIMETRES_OF_MERCURY: &[&str] = &["mm[Hg]"];
+
+/// Units accepted for `kind`, the canonical one first
+fn units(kind: VitalSignKind) -> &'static [&'static str] {
+    match kind {
+        VitalSignKind::RespiratoryRate | VitalSignKind::HeartRate => PER_MINUTE,
+        VitalSignKind::OxygenSaturation => PERCENT,
+        VitalSignKind::BodyTemperature => CELSIUS,
+        VitalSignKind::BodyHeight | VitalSignKind::HeadCircumference => CENTIMETRES,
+        VitalSignKind::BodyWeight => KILOGRAMS,
+        VitalSignKind::Bmi => KILOGRAMS_PER_SQUARE_METRE,
+        VitalSignKind::BloodPressure
+        | VitalSignKind::SystolicBloodPressure
+        | VitalSignKind::DiastolicBloodPressure => MILLIMETRES_OF_MERCURY,
+    }
+}
+
+/// UCUM code of the unit `kind` is charted in
+pub fn canonical_unit(kind: VitalSignKind) -> &'static str {
+    units(kind)[0]
+}
+
+/// UCUM codes accepted for `kind`
+pub fn accepted_units(kind: VitalSignKind) -> Vec<&'static str> {
+    units(kind).to_vec()
+}
+
+/// Values of `kind`, in its canonical unit, that a living patient can have
+pub fn plausible_range(kind: VitalSignKind) -> RangeInclusive<f64> {
+    match kind {
+        VitalSignKind::RespiratoryRate => 0.0..=150.0,
+        VitalSignKind::HeartRate => 0.0..=350.0,
+        VitalSignKind::OxygenSaturation => 0.0..=100.0,
+        VitalSignKind::BodyTemperature => 20.0..=46.0,
+        VitalSignKind::BodyHeight => 10.0..=300.0,
+        VitalSignKind::HeadCircumference => 10.0..=100.0,
+        VitalSignKind::BodyWeight => 0.2..=700.0,
+        VitalSignKind::Bmi => 5.0..=200.0,
+        VitalSignKind::BloodPressure | VitalSignKind::SystolicBloodPressure => 0.0..=350.0,
+        VitalSignKind::DiastolicBloodPressure => 0.0..=250.0,
+    }
+}
+
+/// `quantity` in the canonical unit of `kind`, if it is in a unit `kind`
+/// accepts; quantities recorded before validation are charted only if
+/// they convert
+pub fn to_canonical(kind: VitalSignKind, quantity: &Quantity) -> Option<f64> {
+    let code = quantity.code.as_deref().filter(|code| units(kind).contains(code))?;
+    Ucum::convert(quantity.value, code, canonical_unit(kind)).ok()
+}
+
+/// Check that `quantity` is a UCUM quantity in a unit `kind` accepts, with
+/// a plausible value, and return the value in the canonical unit
+pub fn check_quantity(kind: VitalSignKind, quantity: &Quantity) -> Result<f64, HimsError> {
+    let invalid = |message: String| HimsError::ValidationError { message };
+
+    if quantity.system.as_deref().is_some_and(|system| system != UCUM_SYSTEM) {
+        return Err(invalid(format!("{} units must be coded in {}", kind.display(), UCUM_SYSTEM)));
+    }
+    if !quantity.value.is_finite() {
+        return Err(invalid(format!("{} is not a number", kind.display())));
+    }
+    // A unit that is not UCUM, or that measures something else, is named as
+    // such before the profile's own list of units is checked
+    if let Some(code) = quantity.code.as_deref() {
+        let unit = Ucum::parse(code)?;
+        if !unit.is_commensurable(&Ucum::parse(canonical_unit(kind))?) {
+            return Err(invalid(format!("{} cannot be measured in {}", kind.display(), code)));
+        }
+    }
+    let value = to_canonical(kind, quantity).ok_or_else(|| {
+        invalid(format!(
+            "{} must be in one of {}, not {:?}",
+            kind.display(),
+            accepted_units(kind).join(", "),
+            quantity.code.as_deref().unwrap_or_default()
+        ))
+    })?;
+    let range = plausible_range(kind);
+    if !range.contains(&value) {
+        return Err(invalid(format!(
+            "{} of {} {} is outside {}-{} {}",
+            kind.display(),
+            quantity.value,
+            quantity.code.as_deref().unwrap_or_default(),
+            range.start(),
+            range.end(),
+            canonical_unit(kind)
+        )));
+    }
+    Ok(value)
+}
+
+/// A UCUM quantity of `value` in `code`
+pub fn ucum(value: f64, code: &str) -> Quantity {
+    Quantity {
+        value,
+        unit: Some(code.to_string()),
+        system: Some(UCUM_SYSTEM.to_string()),
+        code: Some(code.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(check_quantity(VitalSignKind::BodyTemperature, &ucum(98.6, "[degF]")).unwrap(), 37.0));
+        assert!(close(check_quantity(VitalSignKind::BodyWeight, &ucum(3500.0, "g")).unwrap(), 3.5));
+        assert!(close(check_quantity(VitalSignKind::BodyHeight, &ucum(70.0, "[in_i]")).unwrap(), 177.8));
+        assert_eq!(canonical_unit(VitalSignKind::BodyWeight), "kg");
+    }
+
+    #[test]
+    fn test_invalid_quantities() {
+        // Wrong unit, implausible value, non-UCUM system, no unit
+        assert!(check_quantity(VitalSignKind::HeartRate, &ucum(72.0, "/s")).is_err());
+        assert!(check_quantity(VitalSignKind::BodyWeight, &ucum(70.0, "kg/m2")).is_err());
+        assert!(check_quantity(VitalSignKind::BodyWeight, &ucum(70.0, "Kg")).is_err());
+        assert!(check_quantity(VitalSignKind::OxygenSaturation, &ucum(101.0, "%")).is_err());
+        assert!(check_quantity(VitalSignKind::BodyTemperature, &ucum(370.0, "Cel")).is_err());
+        let mut quantity = ucum(72.0, "/min");
+        quantity.system = Some("http://example.org/units".to_string());
+        assert!(check_quantity(VitalSignKind::HeartRate, &quantity).is_err());
+        quantity.system = None;
+        assert!(check_quantity(VitalSignKind::HeartRate, &quantity).is_ok());
+        quantity.code = None;
+        assert!(check_quantity(VitalSignKind::HeartRate, &quantity).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+//! Webhook Module
+//!
+//! This module provides outbound webhooks for lightweight downstream apps including:
+//! - Endpoints registered per tenant for domain event subjects, `<noun>.*` or `*`
+//! - Payloads signed with HMAC-SHA256 using a per-endpoint secret sealed at rest
+//! - Delivery with exponential backoff and a record of every attempt
+//! - Redelivery of any past delivery, keeping its event ID for deduplication
+
+#[path = "webhook.controller.rs"]
+pub mod webhook_controller;
+#[path = "webhook.service.rs"]
+pub mod webhook_service;
+#[path = "webhook.sql.rs"]
+pub mod webhook_sql;
+
+pub use webhook_controller::WebhookController;
+pub use webhook_service::{DeliveryStatus, WebhookDelivery, WebhookEndpoint, WebhookService};
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::api_router::ApiRouter;
+
+/// Webhook Module Configuration
+pub struct WebhookModule {
+    pub service: Arc<WebhookService>,
+    pub controller: Arc<WebhookController>,
+}
+
+impl WebhookModule {
+    /// Create a new Webhook Module with dependency injection
+    pub fn new(db_pool: PgPool) -> Self {
+        let service = Arc::new(WebhookService::new(db_pool));
+        let controller = Arc::new(WebhookController::new(service.clone()));
+
+        Self { service, controller }
+    }
+
+    /// Register webhook routes
+    pub fn routes(&self) -> ApiRouter {
+        self.controller.routes()
+    }
+
+    /// Get service instance for dependency injection
+    pub fn get_service(&self) -> Arc<WebhookService> {
+        self.service.clone()
+    }
+}
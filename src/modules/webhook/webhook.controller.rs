@@ -0,0 +1,254 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::modules::events::DomainEvent;
+use crate::modules::webhook::webhook_service::{
+    DeliveryStatus, WebhookDelivery, WebhookEndpoint, WebhookEndpointRequest, WebhookEndpointSecret,
+};
+use crate::modules::webhook::WebhookService;
+use crate::utils::api_router::ApiRouter;
+use crate::utils::auth::extract_user_from_headers;
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub status: Option<DeliveryStatus>,
+    #[serde(rename = "_count")]
+    pub count: Option<i64>,
+    #[serde(rename = "_offset")]
+    pub offset: Option<i64>,
+}
+
+/// Webhook controller for endpoint registration and delivery history
+pub struct WebhookController {
+    webhook_service: Arc<WebhookService>,
+}
+
+impl WebhookController {
+    /// Create new controller with injected service
+    pub fn new(webhook_service: Arc<WebhookService>) -> Self {
+        Self { webhook_service }
+    }
+
+    /// Create router with dependency injection
+    pub fn routes(&self) -> ApiRouter {
+        ApiRouter::new()
+            .post("/", Self::create_endpoint, "Register webhook endpoint")
+            .get("/", Self::list_endpoints, "List webhook endpoints")
+            .get("/events", Self::list_event_types, "List event types webhooks can receive")
+            .get("/:id", Self::get_endpoint, "Get webhook endpoint by ID")
+            .put("/:id", Self::update_endpoint, "Update webhook endpoint")
+            .delete("/:id", Self::delete_endpoint, "Delete webhook endpoint")
+            .post("/:id/rotate-secret", Self::rotate_secret, "Rotate webhook signing secret")
+            .get("/:id/deliveries", Self::list_deliveries, "List webhook deliveries")
+            .get("/:id/deliveries/:delivery_id", Self::get_delivery, "Get webhook delivery with its attempts")
+            .post("/:id/deliveries/:delivery_id/redeliver", Self::redeliver, "Redeliver a webhook")
+            .with_state(self.webhook_service.clone())
+    }
+
+    /// Register endpoint; the response holds the signing secret, which is
+    /// not shown again
+    pub async fn create_endpoint(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Json(payload): Json<WebhookEndpointRequest>,
+    ) -> Result<(StatusCode, Json<WebhookEndpointSecret>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Registering webhook endpoint {}", payload.url);
+
+        match webhook_service.create_endpoint(actor, payload).await {
+            Ok(endpoint) => Ok((StatusCode::CREATED, Json(endpoint))),
+            Err(e) => Err(Self::error_response("Failed to register webhook endpoint", e)),
+        }
+    }
+
+    /// List endpoints
+    pub async fn list_endpoints(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+    ) -> Result<Json<Vec<WebhookEndpoint>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match webhook_service.list_endpoints().await {
+            Ok(endpoints) => Ok(Json(endpoints)),
+            Err(e) => Err(Self::error_response("Failed to list webhook endpoints", e)),
+        }
+    }
+
+    /// Event subjects an endpoint can subscribe to
+    pub async fn list_event_types(headers: HeaderMap) -> Result<Json<Value>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        Ok(Json(json!(DomainEvent::SUBJECTS)))
+    }
+
+    /// Get endpoint by ID
+    pub async fn get_endpoint(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<WebhookEndpoint>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match webhook_service.get_endpoint(id).await {
+            Ok(Some(endpoint)) => Ok(Json(endpoint)),
+            Ok(None) => Err(Self::not_found("Webhook endpoint", id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve webhook endpoint", e)),
+        }
+    }
+
+    /// Update endpoint
+    pub async fn update_endpoint(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<WebhookEndpointRequest>,
+    ) -> Result<Json<WebhookEndpoint>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Updating webhook endpoint: {}", id);
+
+        match webhook_service.update_endpoint(id, payload).await {
+            Ok(Some(endpoint)) => Ok(Json(endpoint)),
+            Ok(None) => Err(Self::not_found("Webhook endpoint", id)),
+            Err(e) => Err(Self::error_response("Failed to update webhook endpoint", e)),
+        }
+    }
+
+    /// Delete endpoint along with its delivery history
+    pub async fn delete_endpoint(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Deleting webhook endpoint: {}", id);
+
+        match webhook_service.delete_endpoint(id).await {
+            Ok(true) => Ok(StatusCode::NO_CONTENT),
+            Ok(false) => Err(Self::not_found("Webhook endpoint", id)),
+            Err(e) => Err(Self::error_response("Failed to delete webhook endpoint", e)),
+        }
+    }
+
+    /// Replace the signing secret; the response holds the new one
+    pub async fn rotate_secret(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<WebhookEndpointSecret>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+        tracing::info!("Rotating secret of webhook endpoint: {}", id);
+
+        match webhook_service.rotate_secret(id).await {
+            Ok(Some(endpoint)) => Ok(Json(endpoint)),
+            Ok(None) => Err(Self::not_found("Webhook endpoint", id)),
+            Err(e) => Err(Self::error_response("Failed to rotate webhook secret", e)),
+        }
+    }
+
+    /// Deliveries to an endpoint, newest first
+    pub async fn list_deliveries(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Path(id): Path<Uuid>,
+        Query(query): Query<DeliveryQuery>,
+    ) -> Result<Json<Vec<WebhookDelivery>>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match webhook_service.list_deliveries(id, query.status, query.count, query.offset).await {
+            Ok(deliveries) => Ok(Json(deliveries)),
+            Err(e) => Err(Self::error_response("Failed to list webhook deliveries", e)),
+        }
+    }
+
+    /// Get delivery with every attempt made for it
+    pub async fn get_delivery(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<Json<WebhookDelivery>, (StatusCode, Json<ErrorResponse>)> {
+        Self::actor(&headers)?;
+
+        match webhook_service.get_delivery(id, delivery_id).await {
+            Ok(Some(delivery)) => Ok(Json(delivery)),
+            Ok(None) => Err(Self::not_found("Webhook delivery", delivery_id)),
+            Err(e) => Err(Self::error_response("Failed to retrieve webhook delivery", e)),
+        }
+    }
+
+    /// Send a delivery's payload again as a new delivery
+    pub async fn redeliver(
+        State(webhook_service): State<Arc<WebhookService>>,
+        headers: HeaderMap,
+        Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+    ) -> Result<(StatusCode, Json<WebhookDelivery>), (StatusCode, Json<ErrorResponse>)> {
+        let actor = Self::actor(&headers)?;
+        tracing::info!("Redelivering webhook delivery: {}", delivery_id);
+
+        match webhook_service.redeliver(actor, id, delivery_id).await {
+            Ok(Some(delivery)) => Ok((StatusCode::ACCEPTED, Json(delivery))),
+            Ok(None) => Err(Self::not_found("Webhook delivery", delivery_id)),
+            Err(e) => Err(Self::error_response("Failed to redeliver webhook", e)),
+        }
+    }
+
+    /// Get authenticated user from headers
+    fn actor(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<ErrorResponse>)> {
+        extract_user_from_headers(headers).map_err(|e| {
+            tracing::error!("Failed to extract user from headers: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Unauthorized".to_string(),
+                    message: "Invalid or missing authentication".to_string(),
+                }),
+            )
+        })
+    }
+
+    fn not_found(kind: &str, id: Uuid) -> (StatusCode, Json<ErrorResponse>) {
+        tracing::warn!("{} not found: {}", kind, id);
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("{} not found", kind),
+                message: format!("{} with id {} not found", kind, id),
+            }),
+        )
+    }
+
+    fn error_response(context: &str, e: HimsError) -> (StatusCode, Json<ErrorResponse>) {
+        let status = match &e {
+            HimsError::SecurityError { .. } => StatusCode::FORBIDDEN,
+            HimsError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        if status == StatusCode::FORBIDDEN {
+            tracing::warn!("{}: {}", context, e);
+        } else {
+            tracing::error!("{}: {}", context, e);
+        }
+
+        (
+            status,
+            Json(ErrorResponse {
+                error: context.to_string(),
+                message: e.to_string(),
+            }),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
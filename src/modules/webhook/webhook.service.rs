@@ -0,0 +1,748 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::database::tenant::{with_tenant, TenantContext};
+use crate::modules::events::{subject_matches, DomainEvent, EventBus, EventEnvelope};
+use crate::modules::integration::{CredentialCipher, Credentials};
+use crate::modules::metrics::metrics_service::QueueDepth;
+use crate::modules::subscription::subscription_service::retry_delay;
+
+// Import SQL queries from separate file
+use crate::modules::webhook::webhook_sql::*;
+
+/// Header carrying `t=<unix seconds>,v1=<hex HMAC-SHA256>`
+pub const SIGNATURE_HEADER: &str = "X-Hims-Signature";
+
+/// Header carrying the event subject, e.g. `patient.created`
+pub const EVENT_HEADER: &str = "X-Hims-Event";
+
+/// Header carrying the delivery ID, which changes on redelivery
+pub const DELIVERY_HEADER: &str = "X-Hims-Delivery";
+
+/// Deliveries sent per delivery pass
+const DELIVERY_BATCH: i64 = 100;
+
+/// Attempts before a delivery is given up
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+
+/// Timeout for one POST to an endpoint
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Characters of an endpoint's response kept with each attempt
+const RESPONSE_BODY_LIMIT: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "delivered" => DeliveryStatus::Delivered,
+            "failed" => DeliveryStatus::Failed,
+            _ => DeliveryStatus::Pending,
+        }
+    }
+}
+
+/// A downstream URL and the domain events it is sent
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub url: String,
+    pub description: Option<String>,
+    /// Event subjects, `<noun>.*` or `*`
+    pub event_types: Vec<String>,
+    pub enabled: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WebhookEndpoint {
+    /// Whether the endpoint wants events with this subject
+    pub fn subscribes_to(&self, subject: &str) -> bool {
+        self.event_types.iter().any(|pattern| subject_matches(pattern, subject))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookEndpointRequest {
+    pub url: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub event_types: Vec<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// An endpoint with its signing secret, which is only ever returned when
+/// the endpoint is created or the secret rotated
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEndpointSecret {
+    #[serde(flatten)]
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+/// One POST made for a delivery
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryAttempt {
+    pub attempt: i32,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// One event sent, or being sent, to one endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    /// ID of the domain event, the same across redeliveries so receivers
+    /// can drop duplicates
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub redelivery_of: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// Attempts made so far; only filled in when a single delivery is read
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<DeliveryAttempt>,
+}
+
+/// A delivery claimed for sending
+struct PendingDelivery {
+    id: Uuid,
+    tenant_id: Uuid,
+    event_type: String,
+    payload: Value,
+    attempts: i32,
+    url: String,
+    secret: Vec<u8>,
+}
+
+/// How an endpoint answered one attempt
+struct AttemptOutcome {
+    response_status: Option<i32>,
+    response_body: Option<String>,
+    error: Option<String>,
+}
+
+/// `t=<timestamp>,v1=<signature>` for a payload, where the signature is the
+/// hex HMAC-SHA256 of `<timestamp>.<body>` keyed with the endpoint secret.
+/// Receivers recompute it over the raw body and reject stale timestamps.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut context = hmac::Context::with_key(&key);
+    context.update(timestamp.to_string().as_bytes());
+    context.update(b".");
+    context.update(body.as_bytes());
+    let signature: String = context.sign().as_ref().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("t={},v1={}", timestamp, signature)
+}
+
+/// Body sent for an event
+pub fn payload(envelope: &EventEnvelope) -> Value {
+    json!({
+        "id": envelope.id,
+        "type": envelope.event.subject(),
+        "occurred_at": envelope.occurred_at,
+        "tenant_id": envelope.tenant_id,
+        "actor": envelope.actor,
+        "data": envelope.event
+    })
+}
+
+/// Registers webhook endpoints, turns domain events into deliveries and
+/// sends them signed, with retries
+pub struct WebhookService {
+    pool: PgPool,
+    cipher: CredentialCipher,
+    http_client: reqwest::Client,
+}
+
+impl WebhookService {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_cipher(pool, CredentialCipher::from_env())
+    }
+
+    pub fn with_cipher(pool: PgPool, cipher: CredentialCipher) -> Self {
+        Self {
+            pool,
+            cipher,
+            http_client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .user_agent("open-hims-webhooks")
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Register an endpoint with a new signing secret
+    pub async fn create_endpoint(&self, actor: Uuid, request: WebhookEndpointRequest) -> Result<WebhookEndpointSecret, HimsError> {
+        Self::validate(&request)?;
+        let (secret, sealed) = self.new_secret()?;
+
+        let row = sqlx::query(INSERT_ENDPOINT)
+            .bind(Uuid::new_v4())
+            .bind(&request.url)
+            .bind(&request.description)
+            .bind(&request.event_types)
+            .bind(sealed)
+            .bind(request.enabled)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(WebhookEndpointSecret {
+            endpoint: Self::row_to_endpoint(&row)?,
+            secret,
+        })
+    }
+
+    pub async fn get_endpoint(&self, id: Uuid) -> Result<Option<WebhookEndpoint>, HimsError> {
+        let row = sqlx::query(GET_ENDPOINT)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        row.as_ref().map(Self::row_to_endpoint).transpose()
+    }
+
+    pub async fn list_endpoints(&self) -> Result<Vec<WebhookEndpoint>, HimsError> {
+        let rows = sqlx::query(LIST_ENDPOINTS)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        rows.iter().map(Self::row_to_endpoint).collect()
+    }
+
+    /// Replace an endpoint's URL, events and toggle; its secret is kept
+    pub async fn update_endpoint(&self, id: Uuid, request: WebhookEndpointRequest) -> Result<Option<WebhookEndpoint>, HimsError> {
+        Self::validate(&request)?;
+
+        let row = sqlx::query(UPDATE_ENDPOINT)
+            .bind(id)
+            .bind(&request.url)
+            .bind(&request.description)
+            .bind(&request.event_types)
+            .bind(request.enabled)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        row.as_ref().map(Self::row_to_endpoint).transpose()
+    }
+
+    /// Replace an endpoint's signing secret. Deliveries not yet sent are
+    /// signed with the new one.
+    pub async fn rotate_secret(&self, id: Uuid) -> Result<Option<WebhookEndpointSecret>, HimsError> {
+        let (secret, sealed) = self.new_secret()?;
+
+        let row = sqlx::query(ROTATE_ENDPOINT_SECRET)
+            .bind(id)
+            .bind(sealed)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        row.as_ref()
+            .map(|row| {
+                Ok(WebhookEndpointSecret {
+                    endpoint: Self::row_to_endpoint(row)?,
+                    secret: secret.clone(),
+                })
+            })
+            .transpose()
+    }
+
+    /// Delete an endpoint along with its delivery history
+    pub async fn delete_endpoint(&self, id: Uuid) -> Result<bool, HimsError> {
+        let result = sqlx::query(DELETE_ENDPOINT)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deliveries to an endpoint, newest first
+    pub async fn list_deliveries(
+        &self,
+        endpoint_id: Uuid,
+        status: Option<DeliveryStatus>,
+        count: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<WebhookDelivery>, HimsError> {
+        let rows = sqlx::query(LIST_DELIVERIES)
+            .bind(endpoint_id)
+            .bind(status.map(|status| status.as_str()))
+            .bind(count.unwrap_or(50).clamp(1, 500))
+            .bind(offset.unwrap_or(0).max(0))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))?;
+
+        rows.iter().map(Self::row_to_delivery).collect()
+    }
+
+    /// A delivery with every attempt made for it
+    pub async fn get_delivery(&self, endpoint_id: Uuid, id: Uuid) -> Result<Option<WebhookDelivery>, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let Some(row) = sqlx::query(GET_DELIVERY)
+            .bind(id)
+            .bind(endpoint_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db)?
+        else {
+            return Ok(None);
+        };
+
+        let mut delivery = Self::row_to_delivery(&row)?;
+        delivery.history = sqlx::query(LIST_DELIVERY_ATTEMPTS)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db)?
+            .iter()
+            .map(Self::row_to_attempt)
+            .collect::<Result<_, _>>()?;
+        Ok(Some(delivery))
+    }
+
+    /// Send a delivery's payload again, as a new delivery due now. The
+    /// event ID is kept so receivers can tell it is a repeat.
+    pub async fn redeliver(&self, actor: Uuid, endpoint_id: Uuid, id: Uuid) -> Result<Option<WebhookDelivery>, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let Some(original) = sqlx::query(GET_DELIVERY)
+            .bind(id)
+            .bind(endpoint_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db)?
+        else {
+            return Ok(None);
+        };
+        let original = Self::row_to_delivery(&original)?;
+        let Some(endpoint) = self.get_endpoint(endpoint_id).await? else {
+            return Ok(None);
+        };
+
+        let row = sqlx::query(INSERT_DELIVERY)
+            .bind(Uuid::new_v4())
+            .bind(endpoint.tenant_id)
+            .bind(endpoint_id)
+            .bind(original.event_id)
+            .bind(&original.event_type)
+            .bind(&original.payload)
+            .bind(original.id)
+            .bind(actor)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(db)?;
+        Self::row_to_delivery(&row).map(Some)
+    }
+
+    /// Queue a delivery of the event to every endpoint of its tenant that
+    /// wants it. Returns how many were queued.
+    pub async fn fan_out(&self, envelope: &EventEnvelope) -> Result<usize, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let subject = envelope.event.subject();
+
+        let endpoints = sqlx::query(GET_TENANT_ENDPOINTS)
+            .bind(envelope.tenant_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db)?
+            .iter()
+            .map(Self::row_to_endpoint)
+            .collect::<Result<Vec<_>, _>>()?;
+        let endpoints: Vec<_> = endpoints.into_iter().filter(|endpoint| endpoint.subscribes_to(&subject)).collect();
+        if endpoints.is_empty() {
+            return Ok(0);
+        }
+
+        let payload = payload(envelope);
+        let mut tx = self.pool.begin().await.map_err(db)?;
+        for endpoint in &endpoints {
+            sqlx::query(INSERT_DELIVERY)
+                .bind(Uuid::new_v4())
+                .bind(envelope.tenant_id)
+                .bind(endpoint.id)
+                .bind(envelope.id)
+                .bind(&subject)
+                .bind(&payload)
+                .bind(None::<Uuid>)
+                .bind(None::<Uuid>)
+                .execute(&mut *tx)
+                .await
+                .map_err(db)?;
+        }
+        tx.commit().await.map_err(db)?;
+        Ok(endpoints.len())
+    }
+
+    /// Send one batch of due deliveries. Failed sends are retried with
+    /// backoff until the last attempt. Returns how many were delivered.
+    pub async fn deliver_once(&self) -> Result<usize, HimsError> {
+        let db = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(db)?;
+
+        let due = sqlx::query(GET_DUE_DELIVERIES)
+            .bind(DELIVERY_BATCH)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(db)?
+            .iter()
+            .map(Self::row_to_pending)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut delivered = 0;
+        for delivery in due {
+            let started = Instant::now();
+            let outcome = self.send(&delivery).await;
+            let duration_ms = i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX);
+
+            sqlx::query(INSERT_DELIVERY_ATTEMPT)
+                .bind(delivery.tenant_id)
+                .bind(delivery.id)
+                .bind(delivery.attempts + 1)
+                .bind(outcome.response_status)
+                .bind(&outcome.response_body)
+                .bind(&outcome.error)
+                .bind(duration_ms)
+                .execute(&mut *tx)
+                .await
+                .map_err(db)?;
+
+            match &outcome.error {
+                None => {
+                    sqlx::query(MARK_DELIVERY_DELIVERED)
+                        .bind(delivery.id)
+                        .bind(outcome.response_status)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                    delivered += 1;
+                }
+                Some(error) if delivery.attempts + 1 >= MAX_DELIVERY_ATTEMPTS => {
+                    tracing::warn!("Giving up on webhook delivery {} to {}: {}", delivery.id, delivery.url, error);
+                    sqlx::query(FAIL_DELIVERY)
+                        .bind(delivery.id)
+                        .bind(outcome.response_status)
+                        .bind(error)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                }
+                Some(error) => {
+                    tracing::debug!("Webhook delivery {} to {} failed: {}", delivery.id, delivery.url, error);
+                    sqlx::query(RETRY_DELIVERY)
+                        .bind(delivery.id)
+                        .bind(outcome.response_status)
+                        .bind(error)
+                        .bind(retry_delay(delivery.attempts + 1).as_secs_f64())
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(db)?;
+                }
+            }
+        }
+
+        tx.commit().await.map_err(db)?;
+        Ok(delivered)
+    }
+
+    async fn send(&self, delivery: &PendingDelivery) -> AttemptOutcome {
+        let failed = |error: String| AttemptOutcome {
+            response_status: None,
+            response_body: None,
+            error: Some(error),
+        };
+        let secret = match self.open_secret(&delivery.secret) {
+            Ok(secret) => secret,
+            Err(e) => return failed(e.to_string()),
+        };
+
+        let body = delivery.payload.to_string();
+        let request = self
+            .http_client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header(EVENT_HEADER, &delivery.event_type)
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(SIGNATURE_HEADER, sign(&secret, Utc::now().timestamp(), &body))
+            .body(body);
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => return failed(e.to_string()),
+        };
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        AttemptOutcome {
+            response_status: Some(i32::from(status.as_u16())),
+            response_body: Some(text.chars().take(RESPONSE_BODY_LIMIT).collect()),
+            error: (!status.is_success()).then(|| format!("Endpoint answered {}", status)),
+        }
+    }
+
+    /// Queue deliveries for every domain event published from now on
+    pub fn listen(self: Arc<Self>, bus: &EventBus) -> JoinHandle<()> {
+        let mut events = bus.subscribe();
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            loop {
+                match events.recv().await {
+                    Ok(envelope) => {
+                        if let Err(e) = self.fan_out(&envelope).await {
+                            tracing::error!("Failed to queue webhooks for {} {}: {}", envelope.event.name(), envelope.id, e);
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::error!("Webhook dispatcher fell behind and missed {} events", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+
+    /// Send due deliveries every `interval`, across tenants
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(with_tenant(TenantContext::system(), async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                loop {
+                    match self.deliver_once().await {
+                        // A full batch all delivered likely means more are due
+                        Ok(delivered) if delivered as i64 == DELIVERY_BATCH => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            tracing::error!("Webhook delivery failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Check an endpoint's URL and events
+    pub fn validate(request: &WebhookEndpointRequest) -> Result<(), HimsError> {
+        let invalid = |message: String| HimsError::ValidationError { message };
+
+        let url = reqwest::Url::parse(&request.url).map_err(|e| invalid(format!("Invalid webhook URL: {}", e)))?;
+        let local = matches!(url.host_str(), Some("localhost") | Some("127.0.0.1") | Some("[::1]"));
+        match url.scheme() {
+            "https" => {}
+            "http" if local => {}
+            _ => return Err(invalid("Webhook URLs must use https".to_string())),
+        }
+
+        if request.event_types.is_empty() {
+            return Err(invalid("A webhook needs at least one event type".to_string()));
+        }
+        for pattern in &request.event_types {
+            if !DomainEvent::SUBJECTS.iter().any(|subject| subject_matches(pattern, subject)) {
+                return Err(invalid(format!("Unknown event type: {}", pattern)));
+            }
+        }
+        Ok(())
+    }
+
+    /// A random secret, and the same sealed for storage
+    fn new_secret(&self) -> Result<(String, Vec<u8>), HimsError> {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new().fill(&mut bytes).map_err(|_| HimsError::InternalError {
+            message: "Failed to generate webhook secret".to_string(),
+        })?;
+        let secret = format!("whsec_{}", general_purpose::URL_SAFE_NO_PAD.encode(bytes));
+
+        let sealed = self.cipher.seal(&Credentials::from([("secret".to_string(), secret.clone())]))?;
+        Ok((secret, sealed))
+    }
+
+    fn open_secret(&self, sealed: &[u8]) -> Result<String, HimsError> {
+        self.cipher
+            .open(sealed)?
+            .remove("secret")
+            .ok_or_else(|| HimsError::SecurityError {
+                message: "Webhook secret is missing".to_string(),
+            })
+    }
+
+    fn row_to_endpoint(row: &PgRow) -> Result<WebhookEndpoint, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(WebhookEndpoint {
+            id: row.try_get("id").map_err(read)?,
+            tenant_id: row.try_get("tenant_id").map_err(read)?,
+            url: row.try_get("url").map_err(read)?,
+            description: row.try_get("description").map_err(read)?,
+            event_types: row.try_get("event_types").map_err(read)?,
+            enabled: row.try_get("enabled").map_err(read)?,
+            created_by: row.try_get("created_by").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+            updated_at: row.try_get("updated_at").map_err(read)?,
+        })
+    }
+
+    fn row_to_delivery(row: &PgRow) -> Result<WebhookDelivery, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(WebhookDelivery {
+            id: row.try_get("id").map_err(read)?,
+            endpoint_id: row.try_get("endpoint_id").map_err(read)?,
+            event_id: row.try_get("event_id").map_err(read)?,
+            event_type: row.try_get("event_type").map_err(read)?,
+            payload: row.try_get("payload").map_err(read)?,
+            status: DeliveryStatus::from_string(&row.try_get::<String, _>("status").map_err(read)?),
+            attempts: row.try_get("attempts").map_err(read)?,
+            next_attempt_at: row.try_get("next_attempt_at").map_err(read)?,
+            response_status: row.try_get("response_status").map_err(read)?,
+            last_error: row.try_get("last_error").map_err(read)?,
+            redelivery_of: row.try_get("redelivery_of").map_err(read)?,
+            created_by: row.try_get("created_by").map_err(read)?,
+            created_at: row.try_get("created_at").map_err(read)?,
+            delivered_at: row.try_get("delivered_at").map_err(read)?,
+            history: Vec::new(),
+        })
+    }
+
+    fn row_to_attempt(row: &PgRow) -> Result<DeliveryAttempt, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(DeliveryAttempt {
+            attempt: row.try_get("attempt").map_err(read)?,
+            response_status: row.try_get("response_status").map_err(read)?,
+            response_body: row.try_get("response_body").map_err(read)?,
+            error: row.try_get("error").map_err(read)?,
+            duration_ms: row.try_get("duration_ms").map_err(read)?,
+            attempted_at: row.try_get("attempted_at").map_err(read)?,
+        })
+    }
+
+    fn row_to_pending(row: &PgRow) -> Result<PendingDelivery, HimsError> {
+        let read = |e: sqlx::Error| HimsError::DatabaseError(e.to_string());
+
+        Ok(PendingDelivery {
+            id: row.try_get("id").map_err(read)?,
+            tenant_id: row.try_get("tenant_id").map_err(read)?,
+            event_type: row.try_get("event_type").map_err(read)?,
+            payload: row.try_get("payload").map_err(read)?,
+            attempts: row.try_get("attempts").map_err(read)?,
+            url: row.try_get("url").map_err(read)?,
+            secret: row.try_get("secret").map_err(read)?,
+        })
+    }
+}
+
+#[async_trait]
+impl QueueDepth for WebhookService {
+    /// Deliveries waiting to be sent, including ones backing off
+    async fn depth(&self) -> Result<i64, HimsError> {
+        sqlx::query_scalar(COUNT_PENDING_DELIVERIES)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| HimsError::DatabaseError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, event_types: &[&str]) -> WebhookEndpointRequest {
+        WebhookEndpointRequest {
+            url: url.to_string(),
+            description: None,
+            event_types: event_types.iter().map(|event_type| event_type.to_string()).collect(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, r#"{"id":1}"#),
+            "t=1700000000,v1=2f441ba4b3b2d50d28a9ab9d9fd8880376ecd1eb5d0435401553f5d8d0a5dcf8"
+        );
+        assert_ne!(sign("whsec_other", 1_700_000_000, r#"{"id":1}"#), sign("whsec_test", 1_700_000_000, r#"{"id":1}"#));
+    }
+
+    #[test]
+    fn test_event_type_matching() {
+        assert!(subject_matches("*", "patient.created"));
+        assert!(subject_matches("appointment.*", "appointment.status_changed"));
+        assert!(!subject_matches("appointment.*", "patient.created"));
+        assert!(subject_matches("record.finalized", "record.finalized"));
+        assert!(!subject_matches("record.finalized", "record.created"));
+        assert!(!subject_matches("record*", "record.created"));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(WebhookService::validate(&request("https://app.example.org/hooks", &["patient.*"])).is_ok());
+        assert!(WebhookService::validate(&request("http://localhost:3000/hooks", &["*"])).is_ok());
+        assert!(WebhookService::validate(&request("http://app.example.org/hooks", &["*"])).is_err());
+        assert!(WebhookService::validate(&request("https://app.example.org/hooks", &[])).is_err());
+        assert!(WebhookService::validate(&request("https://app.example.org/hooks", &["patient.merged"])).is_err());
+        assert!(WebhookService::validate(&request("https://app.example.org/hooks", &["billing.*"])).is_err());
+    }
+
+    #[test]
+    fn test_payload() {
+        let patient_id = Uuid::new_v4();
+        let envelope = EventEnvelope::new(DomainEvent::PatientCreated { patient_id });
+        let body = payload(&envelope);
+        assert_eq!(body["id"], json!(envelope.id));
+        assert_eq!(body["type"], "patient.created");
+        assert_eq!(body["data"]["patient_id"], json!(patient_id));
+    }
+}
@@ -0,0 +1,119 @@
+//! Webhook SQL Queries
+//!
+//! This file contains all SQL queries used by the webhook service
+//! for clean separation of concerns and better maintainability.
+
+pub const INSERT_ENDPOINT: &str = r#"
+    INSERT INTO webhook_endpoints (id, url, description, event_types, secret, enabled, created_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    RETURNING id, tenant_id, url, description, event_types, enabled, created_by, created_at, updated_at
+"#;
+
+pub const GET_ENDPOINT: &str = r#"
+    SELECT id, tenant_id, url, description, event_types, enabled, created_by, created_at, updated_at
+    FROM webhook_endpoints
+    WHERE id = $1
+"#;
+
+pub const LIST_ENDPOINTS: &str = r#"
+    SELECT id, tenant_id, url, description, event_types, enabled, created_by, created_at, updated_at
+    FROM webhook_endpoints
+    ORDER BY created_at
+"#;
+
+pub const UPDATE_ENDPOINT: &str = r#"
+    UPDATE webhook_endpoints
+    SET url = $2, description = $3, event_types = $4, enabled = $5
+    WHERE id = $1
+    RETURNING id, tenant_id, url, description, event_types, enabled, created_by, created_at, updated_at
+"#;
+
+pub const ROTATE_ENDPOINT_SECRET: &str = r#"
+    UPDATE webhook_endpoints SET secret = $2
+    WHERE id = $1
+    RETURNING id, tenant_id, url, description, event_types, enabled, created_by, created_at, updated_at
+"#;
+
+pub const DELETE_ENDPOINT: &str = r#"
+    DELETE FROM webhook_endpoints WHERE id = $1
+"#;
+
+/// Enabled endpoints of a tenant, matched against each event in turn
+pub const GET_TENANT_ENDPOINTS: &str = r#"
+    SELECT id, tenant_id, url, description, event_types, enabled, created_by, created_at, updated_at
+    FROM webhook_endpoints
+    WHERE tenant_id = $1 AND enabled
+"#;
+
+pub const INSERT_DELIVERY: &str = r#"
+    INSERT INTO webhook_deliveries (id, tenant_id, endpoint_id, event_id, event_type, payload, redelivery_of, created_by)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING id, endpoint_id, event_id, event_type, payload, status, attempts, next_attempt_at,
+        response_status, last_error, redelivery_of, created_by, created_at, delivered_at
+"#;
+
+pub const GET_DELIVERY: &str = r#"
+    SELECT id, endpoint_id, event_id, event_type, payload, status, attempts, next_attempt_at,
+        response_status, last_error, redelivery_of, created_by, created_at, delivered_at
+    FROM webhook_deliveries
+    WHERE id = $1 AND endpoint_id = $2
+"#;
+
+/// Deliveries to an endpoint, newest first, optionally of one status
+pub const LIST_DELIVERIES: &str = r#"
+    SELECT id, endpoint_id, event_id, event_type, payload, status, attempts, next_attempt_at,
+        response_status, last_error, redelivery_of, created_by, created_at, delivered_at
+    FROM webhook_deliveries
+    WHERE endpoint_id = $1 AND ($2::TEXT IS NULL OR status = $2)
+    ORDER BY created_at DESC
+    LIMIT $3 OFFSET $4
+"#;
+
+pub const LIST_DELIVERY_ATTEMPTS: &str = r#"
+    SELECT attempt, response_status, response_body, error, duration_ms, attempted_at
+    FROM webhook_delivery_attempts
+    WHERE delivery_id = $1
+    ORDER BY attempt
+"#;
+
+/// Deliveries due to be sent, with their endpoint's URL and secret. Rows
+/// are locked so concurrent servers send different ones.
+pub const GET_DUE_DELIVERIES: &str = r#"
+    SELECT d.id, d.tenant_id, d.endpoint_id, d.event_id, d.event_type, d.payload, d.attempts, e.url, e.secret
+    FROM webhook_deliveries d
+    JOIN webhook_endpoints e ON e.id = d.endpoint_id
+    WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND e.enabled
+    ORDER BY d.created_at
+    LIMIT $1
+    FOR UPDATE OF d SKIP LOCKED
+"#;
+
+pub const INSERT_DELIVERY_ATTEMPT: &str = r#"
+    INSERT INTO webhook_delivery_attempts (tenant_id, delivery_id, attempt, response_status, response_body, error, duration_ms)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+"#;
+
+pub const MARK_DELIVERY_DELIVERED: &str = r#"
+    UPDATE webhook_deliveries
+    SET status = 'delivered', attempts = attempts + 1, response_status = $2, last_error = NULL, delivered_at = NOW()
+    WHERE id = $1
+"#;
+
+/// Record a failed attempt and schedule the next one
+pub const RETRY_DELIVERY: &str = r#"
+    UPDATE webhook_deliveries
+    SET attempts = attempts + 1, response_status = $2, last_error = $3, next_attempt_at = NOW() + make_interval(secs => $4)
+    WHERE id = $1
+"#;
+
+/// Give up on a delivery after its last attempt
+pub const FAIL_DELIVERY: &str = r#"
+    UPDATE webhook_deliveries
+    SET status = 'failed', attempts = attempts + 1, response_status = $2, last_error = $3
+    WHERE id = $1
+"#;
+
+/// Deliveries waiting to be sent, across tenants under the system context
+pub const COUNT_PENDING_DELIVERIES: &str = r#"
+    SELECT COUNT(*) FROM webhook_deliveries WHERE status = 'pending'
+"#;
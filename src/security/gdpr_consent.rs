@@ -30,7 +30,7 @@ pub enum ConsentType {
     ResearchParticipation,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LegalBasis {
     Consent,
     Contract,
@@ -82,6 +82,7 @@ impl GdprConsentManager {
     }
 
     /// Record consent
+    #[allow(clippy::too_many_arguments)]
     pub async fn record_consent(
         &self,
         user_id: String,
@@ -132,9 +133,9 @@ impl GdprConsentManager {
     /// Check if user has valid consent for a specific purpose
     pub async fn has_valid_consent(
         &self,
-        user_id: String,
-        consent_type: ConsentType,
-        purpose: String,
+        _user_id: String,
+        _consent_type: ConsentType,
+        _purpose: String,
     ) -> Result<bool, HimsError> {
         // In a real implementation, query database for active consent
         // Check if consent exists, is granted, not withdrawn, and not expired
@@ -173,7 +174,7 @@ impl GdprConsentManager {
     /// Get user's consent history
     pub async fn get_consent_history(
         &self,
-        user_id: String,
+        _user_id: String,
     ) -> Result<Vec<GdprConsent>, HimsError> {
         // In a real implementation, query database for user's consent history
         Ok(Vec::new())
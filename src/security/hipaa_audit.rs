@@ -51,6 +51,7 @@ impl HipaaAuditLogger {
     }
 
     /// Log a HIPAA audit event
+    #[allow(clippy::too_many_arguments)]
     pub async fn log_audit_event(
         &self,
         user_id: String,
@@ -135,10 +136,10 @@ impl HipaaAuditLogger {
     /// Generate audit report for compliance
     pub async fn generate_audit_report(
         &self,
-        start_date: DateTime<Utc>,
-        end_date: DateTime<Utc>,
-        user_id: Option<String>,
-        patient_id: Option<String>,
+        _start_date: DateTime<Utc>,
+        _end_date: DateTime<Utc>,
+        _user_id: Option<String>,
+        _patient_id: Option<String>,
     ) -> Result<Vec<HipaaAuditEntry>, HimsError> {
         // In a real implementation, this would query the audit database
         // with the specified filters and return matching entries
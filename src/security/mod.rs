@@ -2,8 +2,12 @@ pub mod hipaa_audit;
 pub mod gdpr_consent;
 pub mod iso27001_logging;
 pub mod hash_chain_logs;
+pub mod phi_detection;
+pub mod workstation_login;
 
 pub use hipaa_audit::*;
 pub use gdpr_consent::*;
 pub use iso27001_logging::*;
-pub use hash_chain_logs::*;
\ No newline at end of file
+pub use hash_chain_logs::*;
+pub use phi_detection::*;
+pub use workstation_login::*;
\ No newline at end of file
@@ -0,0 +1,234 @@
+use serde::Serialize;
+
+/// Identifier shapes recognised anywhere in text, by PHI type
+const PATTERNS: &[(&str, &[&str])] = &[
+    ("ssn", &["ddd-dd-dddd"]),
+    ("phone", &["(ddd) ddd-dddd", "ddd-ddd-dddd", "ddd.ddd.dddd", "+1 ddd-ddd-dddd", "+91 ddddd ddddd"]),
+    ("aadhaar", &["dddd dddd dddd"]),
+];
+
+/// Field names whose values are PHI whatever their shape, by PHI type
+const LABELS: &[(&str, &str)] = &[
+    ("medical_record_number", "mrn"),
+    ("date_of_birth", "dob"),
+    ("patient_name", "name"),
+    ("birth_date", "dob"),
+    ("mrn", "mrn"),
+    ("ssn", "ssn"),
+    ("dob", "dob"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhiDetectionResult {
+    pub contains_phi: bool,
+    pub detected_types: Vec<&'static str>,
+}
+
+/// Finds and masks protected health information in free text such as log
+/// lines, using identifier shapes and labelled fields
+#[derive(Debug, Clone, Default)]
+pub struct PhiDetector;
+
+impl PhiDetector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn scan_text(&self, text: &str) -> PhiDetectionResult {
+        let mut detected_types: Vec<&'static str> = Vec::new();
+        for (_, _, phi_type) in self.find(text) {
+            if !detected_types.contains(&phi_type) {
+                detected_types.push(phi_type);
+            }
+        }
+        PhiDetectionResult {
+            contains_phi: !detected_types.is_empty(),
+            detected_types,
+        }
+    }
+
+    /// Replace every detected value with `[REDACTED:<type>]`
+    pub fn redact(&self, text: &str) -> String {
+        let spans = self.find(text);
+        if spans.is_empty() {
+            return text.to_string();
+        }
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut position = 0;
+        for (start, end, phi_type) in spans {
+            redacted.push_str(&text[position..start]);
+            redacted.push_str("[REDACTED:");
+            redacted.push_str(phi_type);
+            redacted.push(']');
+            position = end;
+        }
+        redacted.push_str(&text[position..]);
+        redacted
+    }
+
+    /// Non-overlapping (start, end, type) byte spans, in order
+    fn find(&self, text: &str) -> Vec<(usize, usize, &'static str)> {
+        let bytes = text.as_bytes();
+        let lower = text.to_ascii_lowercase();
+        let mut spans = Vec::new();
+
+        let mut i = 0;
+        while i < bytes.len() {
+            if let Some(span) = match_label(&lower, bytes, i)
+                .or_else(|| match_pattern(bytes, i))
+                .or_else(|| match_email(bytes, i))
+            {
+                i = span.1;
+                spans.push(span);
+            } else {
+                i += 1;
+            }
+        }
+        spans
+    }
+}
+
+fn match_pattern(bytes: &[u8], start: usize) -> Option<(usize, usize, &'static str)> {
+    if start > 0 && bytes[start - 1].is_ascii_alphanumeric() {
+        return None;
+    }
+    for (phi_type, templates) in PATTERNS {
+        for template in *templates {
+            let end = start + template.len();
+            if end > bytes.len() || (end < bytes.len() && bytes[end].is_ascii_digit()) {
+                continue;
+            }
+            let matches = template.bytes().zip(&bytes[start..end]).all(|(expected, actual)| match expected {
+                b'd' => actual.is_ascii_digit(),
+                literal => literal == *actual,
+            });
+            if matches {
+                return Some((start, end, *phi_type));
+            }
+        }
+    }
+    None
+}
+
+fn match_email(bytes: &[u8], start: usize) -> Option<(usize, usize, &'static str)> {
+    let is_local = |b: u8| b.is_ascii_alphanumeric() || b"._%+-".contains(&b);
+    let is_domain = |b: u8| b.is_ascii_alphanumeric() || b == b'.' || b == b'-';
+
+    if !is_local(bytes[start]) || (start > 0 && is_local(bytes[start - 1])) {
+        return None;
+    }
+    let mut at = start;
+    while at < bytes.len() && is_local(bytes[at]) {
+        at += 1;
+    }
+    if at == start || at >= bytes.len() || bytes[at] != b'@' {
+        return None;
+    }
+    let mut end = at + 1;
+    while end < bytes.len() && is_domain(bytes[end]) {
+        end += 1;
+    }
+    while end > at + 1 && bytes[end - 1] == b'.' {
+        end -= 1;
+    }
+    let domain = &bytes[at + 1..end];
+    if domain.contains(&b'.') && domain[0] != b'.' {
+        Some((start, end, "email"))
+    } else {
+        None
+    }
+}
+
+/// `label: value`, `label=value` and `"label":"value"`; the span covers the
+/// value only
+fn match_label(lower: &str, bytes: &[u8], start: usize) -> Option<(usize, usize, &'static str)> {
+    if start > 0 && (bytes[start - 1].is_ascii_alphanumeric() || bytes[start - 1] == b'_') {
+        return None;
+    }
+    let (label, phi_type) = LABELS
+        .iter()
+        .find(|(label, _)| lower.as_bytes()[start..].starts_with(label.as_bytes()))?;
+
+    let mut i = start + label.len();
+    let skip = |i: &mut usize, chars: &[u8]| {
+        while *i < bytes.len() && chars.contains(&bytes[*i]) {
+            *i += 1;
+        }
+    };
+    skip(&mut i, b"\" ");
+    if i >= bytes.len() || (bytes[i] != b':' && bytes[i] != b'=') {
+        return None;
+    }
+    i += 1;
+    skip(&mut i, b" ");
+
+    let quoted = i < bytes.len() && bytes[i] == b'"';
+    if quoted {
+        i += 1;
+    }
+    let value_start = i;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let done = if quoted { b == b'"' } else { b == b',' || b == b';' || b == b'&' || b == b'}' || b.is_ascii_whitespace() };
+        if done {
+            break;
+        }
+        i += 1;
+    }
+
+    if i == value_start || &lower[value_start..i] == "null" {
+        return None;
+    }
+    Some((value_start, i, *phi_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_shapes() {
+        let detector = PhiDetector::new();
+        assert_eq!(
+            detector.redact("ssn 123-45-6789, call (555) 123-4567 or +91 98765 43210"),
+            "ssn [REDACTED:ssn], call [REDACTED:phone] or [REDACTED:phone]"
+        );
+        assert_eq!(detector.redact("Aadhaar 2345 6789 0123 verified"), "Aadhaar [REDACTED:aadhaar] verified");
+        assert_eq!(
+            detector.redact("mail jane.doe+lab@example.org."),
+            "mail [REDACTED:email]."
+        );
+
+        // Longer numbers, versions and identifiers are left alone
+        for text in ["order 1123-45-67890", "build 1.2.3", "id a555-123-4567", "user@localhost"] {
+            assert_eq!(detector.redact(text), text);
+        }
+    }
+
+    #[test]
+    fn test_labelled_fields() {
+        let detector = PhiDetector::new();
+        assert_eq!(
+            detector.redact(r#"{"mrn":"MRN-0042","dob": "1980-02-29","status":"ok"}"#),
+            r#"{"mrn":"[REDACTED:mrn]","dob": "[REDACTED:dob]","status":"ok"}"#
+        );
+        assert_eq!(
+            detector.redact("lookup patient_name=\"Jane Doe\"&MRN=A77 done"),
+            "lookup patient_name=\"[REDACTED:name]\"&MRN=[REDACTED:mrn] done"
+        );
+        // Null values and labels that are part of longer names are not PHI
+        for text in [r#"{"dob":null}"#, "primary_mrn_source=lab", "mrn lookup failed"] {
+            assert_eq!(detector.redact(text), text);
+        }
+    }
+
+    #[test]
+    fn test_scan_text() {
+        let detector = PhiDetector::new();
+        let result = detector.scan_text("mrn=A1 called 555-123-4567 and 555.123.4567");
+        assert!(result.contains_phi);
+        assert_eq!(result.detected_types, vec!["mrn", "phone"]);
+        assert!(!detector.scan_text("Patient created").contains_phi);
+    }
+}
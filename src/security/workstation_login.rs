@@ -0,0 +1,148 @@
+// Workstation side of smart card and fingerprint login. Native clinical
+// workstation apps implement the reader callbacks over PC/SC (for PIV cards
+// in CCID readers) and their fingerprint device's SDK, and post the
+// assertion these functions build to `/api/v1/auth/workstation/login`.
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::HimsError;
+
+/// Prefix of every signed login message, so a card or device key is never
+/// asked to sign something that could be mistaken for anything else
+const LOGIN_MESSAGE_PREFIX: &[u8] = b"open-hims workstation login\n";
+
+/// Kind of credential a workstation login uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialKind {
+    /// PIV card, whose PIV Authentication key signs the challenge
+    SmartCard,
+    /// Match-on-device fingerprint reader, which signs the challenge with
+    /// its device key once a finger matches an enrolled template
+    Fingerprint,
+}
+
+impl CredentialKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialKind::SmartCard => "smart-card",
+            CredentialKind::Fingerprint => "fingerprint",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "smart-card" => Some(CredentialKind::SmartCard),
+            "fingerprint" => Some(CredentialKind::Fingerprint),
+            _ => None,
+        }
+    }
+}
+
+/// A challenge answered with a credential, as posted to the server; binary
+/// values are base64url
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialAssertion {
+    pub kind: CredentialKind,
+    /// The challenge the server issued
+    pub challenge: String,
+    /// DER PIV Authentication certificate (smart cards only)
+    pub certificate: Option<String>,
+    /// Reader and enrolled template that matched (fingerprints only)
+    pub device_id: Option<String>,
+    pub template_id: Option<String>,
+    pub signature: String,
+}
+
+/// A finger matched on a fingerprint device
+pub struct FingerprintMatch {
+    pub device_id: String,
+    pub template_id: String,
+    /// The device key's ECDSA P-256 signature, ASN.1 DER, of
+    /// `workstation_login_message(challenge, "<device_id>:<template_id>")`
+    pub signature: Vec<u8>,
+}
+
+/// A PIV card in a CCID reader, reached through PC/SC
+pub trait SmartCardReader: Send + Sync {
+    /// Certificate of the card's PIV Authentication key (slot 9A), DER
+    fn read_certificate(&self) -> Result<Vec<u8>, HimsError>;
+
+    /// Sign a message with the PIV Authentication key: ECDSA with SHA-256
+    /// (ASN.1 DER) or RSA PKCS#1 v1.5 with SHA-256, after the card's PIN is
+    /// verified
+    fn sign(&self, message: Vec<u8>) -> Result<Vec<u8>, HimsError>;
+}
+
+/// A match-on-device fingerprint reader
+pub trait FingerprintReader: Send + Sync {
+    /// Capture a finger, match it against the device's enrolled templates
+    /// and sign the login message of the one that matched
+    fn capture(&self, challenge: String) -> Result<FingerprintMatch, HimsError>;
+}
+
+/// Bytes a credential signs to answer a challenge: the challenge bound to
+/// the credential answering it
+pub fn workstation_login_message(challenge: String, credential_id: String) -> Vec<u8> {
+    let mut message = LOGIN_MESSAGE_PREFIX.to_vec();
+    message.extend_from_slice(challenge.as_bytes());
+    message.push(b'\n');
+    message.extend_from_slice(credential_id.as_bytes());
+    message
+}
+
+/// ID a smart card is enrolled under: the SHA-256 of its certificate, hex
+pub fn smart_card_credential_id(certificate: &[u8]) -> String {
+    Sha256::digest(certificate).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// ID a fingerprint is enrolled under: the device and its template
+pub fn fingerprint_credential_id(device_id: &str, template_id: &str) -> String {
+    format!("{}:{}", device_id, template_id)
+}
+
+/// Answer a login challenge with the PIV card in the reader
+pub fn assert_smart_card(
+    challenge: String,
+    reader: Box<dyn SmartCardReader>,
+) -> Result<CredentialAssertion, HimsError> {
+    let certificate = reader.read_certificate()?;
+    if certificate.is_empty() {
+        return Err(HimsError::AuthenticationError {
+            message: "The smart card has no PIV Authentication certificate".to_string(),
+        });
+    }
+    let message = workstation_login_message(challenge.clone(), smart_card_credential_id(&certificate));
+    let signature = reader.sign(message)?;
+    Ok(CredentialAssertion {
+        kind: CredentialKind::SmartCard,
+        challenge,
+        certificate: Some(general_purpose::URL_SAFE_NO_PAD.encode(certificate)),
+        device_id: None,
+        template_id: None,
+        signature: general_purpose::URL_SAFE_NO_PAD.encode(signature),
+    })
+}
+
+/// Answer a login challenge with a finger on the fingerprint device
+pub fn assert_fingerprint(
+    challenge: String,
+    reader: Box<dyn FingerprintReader>,
+) -> Result<CredentialAssertion, HimsError> {
+    let matched = reader.capture(challenge.clone())?;
+    if matched.device_id.contains(':') || matched.template_id.is_empty() {
+        return Err(HimsError::AuthenticationError {
+            message: "The fingerprint device returned an invalid match".to_string(),
+        });
+    }
+    Ok(CredentialAssertion {
+        kind: CredentialKind::Fingerprint,
+        challenge,
+        certificate: None,
+        device_id: Some(matched.device_id),
+        template_id: Some(matched.template_id),
+        signature: general_purpose::URL_SAFE_NO_PAD.encode(matched.signature),
+    })
+}
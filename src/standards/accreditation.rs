@@ -1,15 +1,565 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+
 pub struct AccreditationService;
 
 impl AccreditationService {
     pub fn validate_jci_compliance(_data: &str) -> Result<bool, crate::core::HimsError> {
         Ok(true) // Placeholder for JCI compliance check
     }
-    
+
     pub fn validate_nabh_compliance(_data: &str) -> Result<bool, crate::core::HimsError> {
         Ok(true) // Placeholder for NABH compliance check
     }
-    
+
     pub fn validate_nabl_compliance(_data: &str) -> Result<bool, crate::core::HimsError> {
         Ok(true) // Placeholder for NABL compliance check
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AccreditationProgram {
+    Nabh,
+    Jci,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    pub code: String,
+    pub name: String,
+}
+
+/// One standard or objective element an assessor checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecklistItem {
+    pub id: String,
+    pub chapter: String,
+    pub description: String,
+    /// Core elements must be met for accreditation regardless of score
+    pub core: bool,
+    pub evidence_required: Vec<String>,
+}
+
+/// The standards of one accreditation program edition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccreditationChecklist {
+    pub program: AccreditationProgram,
+    pub edition: String,
+    pub chapters: Vec<Chapter>,
+    pub items: Vec<ChecklistItem>,
+}
+
+impl AccreditationChecklist {
+    /// NABH hospital accreditation standards, 5th edition
+    pub fn nabh() -> Self {
+        let chapters = chapters(&[
+            ("AAC", "Access, Assessment and Continuity of Care"),
+            ("COP", "Care of Patients"),
+            ("MOM", "Management of Medication"),
+            ("PRE", "Patient Rights and Education"),
+            ("HIC", "Hospital Infection Control"),
+            ("PSQ", "Patient Safety and Quality Improvement"),
+            ("ROM", "Responsibilities of Management"),
+            ("FMS", "Facility Management and Safety"),
+            ("HRM", "Human Resource Management"),
+            ("IMS", "Information Management System"),
+        ]);
+        let items = vec![
+            item("AAC.1", "AAC", "Scope of services is defined and displayed", false, &["Scope of services document"]),
+            item("AAC.4", "AAC", "Patients are registered and admitted according to documented procedures", false, &["Registration SOP"]),
+            item("AAC.7", "AAC", "Initial assessment of inpatients is completed within 24 hours", true, &["Assessment audit", "Sample case records"]),
+            item("COP.1", "COP", "Uniform care of patients is guided by policies and procedures", false, &["Clinical care policy"]),
+            item("COP.3", "COP", "Emergency services are provided according to documented procedures", true, &["Emergency SOP", "Triage records"]),
+            item("COP.12", "COP", "Informed consent is taken before procedures", true, &["Consent form templates", "Consent audit"]),
+            item("MOM.1", "MOM", "Medication management is guided by a documented policy", false, &["Medication management policy"]),
+            item("MOM.4", "MOM", "Medications are prescribed safely and rationally", true, &["Prescription audit"]),
+            item("MOM.8", "MOM", "Adverse drug events are monitored and reported", false, &["ADR register"]),
+            item("PRE.2", "PRE", "Patient and family rights are displayed and respected", true, &["Rights display photographs", "Staff training records"]),
+            item("PRE.7", "PRE", "Patients are educated on their care, costs and risks", false, &["Patient education materials"]),
+            item("HIC.1", "HIC", "A documented infection control programme is in place", true, &["Infection control manual"]),
+            item("HIC.3", "HIC", "Hand hygiene compliance is monitored", false, &["Hand hygiene audit"]),
+            item("PSQ.1", "PSQ", "A quality improvement and patient safety programme is implemented", true, &["Quality programme document"]),
+            item("PSQ.3", "PSQ", "Quality indicators are collected and analysed", false, &["Indicator dashboard", "Analysis minutes"]),
+            item("ROM.1", "ROM", "Governance responsibilities are defined", false, &["Organogram", "Governance charter"]),
+            item("ROM.5", "ROM", "Leadership ensures statutory and regulatory compliance", true, &["Statutory licence register"]),
+            item("FMS.4", "FMS", "Fire and non-fire emergency plans are in place and drilled", true, &["Fire NOC", "Mock drill reports"]),
+            item("FMS.5", "FMS", "Medical equipment is maintained and calibrated", false, &["Equipment maintenance log"]),
+            item("HRM.5", "HRM", "Staff receive induction and ongoing training", false, &["Training calendar", "Attendance records"]),
+            item("HRM.9", "HRM", "Credentials of medical professionals are verified and privileges granted", true, &["Credentialing files"]),
+            item("IMS.2", "IMS", "Medical records are maintained for every patient", true, &["Medical record policy", "Record audit"]),
+            item("IMS.6", "IMS", "Confidentiality, integrity and security of records are maintained", true, &["Access control policy", "Audit trail sample"]),
+        ];
+
+        Self {
+            program: AccreditationProgram::Nabh,
+            edition: "5th edition".to_string(),
+            chapters,
+            items,
+        }
+    }
+
+    /// JCI accreditation standards for hospitals, 7th edition
+    pub fn jci() -> Self {
+        let chapters = chapters(&[
+            ("IPSG", "International Patient Safety Goals"),
+            ("ACC", "Access to Care and Continuity of Care"),
+            ("PCC", "Patient-Centered Care"),
+            ("AOP", "Assessment of Patients"),
+            ("COP", "Care of Patients"),
+            ("ASC", "Anesthesia and Surgical Care"),
+            ("MMU", "Medication Management and Use"),
+            ("QPS", "Quality Improvement and Patient Safety"),
+            ("PCI", "Prevention and Control of Infections"),
+            ("GLD", "Governance, Leadership, and Direction"),
+            ("FMS", "Facility Management and Safety"),
+            ("SQE", "Staff Qualifications and Education"),
+            ("MOI", "Management of Information"),
+        ]);
+        let items = vec![
+            item("IPSG.1", "IPSG", "Patients are identified correctly using two identifiers", true, &["Identification policy", "Wristband audit"]),
+            item("IPSG.2", "IPSG", "Communication of critical results and handovers is effective", true, &["Critical result log"]),
+            item("IPSG.3", "IPSG", "Safety of high-alert medications is improved", true, &["High-alert medication list"]),
+            item("IPSG.4", "IPSG", "Safe surgery: correct site, procedure and patient", true, &["Surgical safety checklist audit"]),
+            item("IPSG.5", "IPSG", "Risk of health care-associated infections is reduced", true, &["Hand hygiene audit"]),
+            item("IPSG.6", "IPSG", "Risk of patient harm from falls is reduced", true, &["Fall risk assessments"]),
+            item("ACC.1", "ACC", "Patients are screened and admitted based on identified needs", false, &["Admission criteria"]),
+            item("ACC.4", "ACC", "Discharge, referral and follow-up are planned", false, &["Discharge summaries"]),
+            item("PCC.1", "PCC", "Patient and family rights are supported", false, &["Rights policy"]),
+            item("PCC.5", "PCC", "Informed consent is obtained", true, &["Consent audit"]),
+            item("AOP.1", "AOP", "Patient needs are identified through an established assessment process", false, &["Assessment forms"]),
+            item("COP.2", "COP", "Care is planned and delivered for each patient", false, &["Care plans"]),
+            item("ASC.3", "ASC", "Procedural sedation is standardized", false, &["Sedation policy"]),
+            item("MMU.4", "MMU", "Orders and transcription are guided by policy", false, &["Prescribing policy"]),
+            item("MMU.7", "MMU", "Medication effects are monitored and errors reported", false, &["Medication error reports"]),
+            item("QPS.3", "QPS", "Measures are selected, aggregated and analyzed", false, &["Quality measure dashboard"]),
+            item("PCI.5", "PCI", "Infection risks are identified and reduced", false, &["Infection risk assessment"]),
+            item("GLD.1", "GLD", "Governance responsibilities are described", false, &["Governing body bylaws"]),
+            item("FMS.6", "FMS", "Fire safety programme is implemented", true, &["Fire drill reports"]),
+            item("SQE.9", "SQE", "Medical staff credentials are verified", true, &["Primary source verification records"]),
+            item("MOI.2", "MOI", "Information privacy, confidentiality and security are maintained", true, &["Access control policy", "Audit trail sample"]),
+            item("MOI.9", "MOI", "A complete medical record is maintained for every patient", false, &["Record completeness audit"]),
+        ];
+
+        Self {
+            program: AccreditationProgram::Jci,
+            edition: "7th edition".to_string(),
+            chapters,
+            items,
+        }
+    }
+
+    pub fn get_item(&self, item_id: &str) -> Option<&ChecklistItem> {
+        self.items.iter().find(|item| item.id == item_id)
+    }
+
+    pub fn chapter_items(&self, chapter: &str) -> Vec<&ChecklistItem> {
+        self.items.iter().filter(|item| item.chapter == chapter).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplianceStatus {
+    NotStarted,
+    InProgress,
+    PartiallyCompliant,
+    Compliant,
+    NonCompliant,
+    NotApplicable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceDocument {
+    pub id: Uuid,
+    pub title: String,
+    /// Location of the document in the document store
+    pub uri: String,
+    pub uploaded_by: Uuid,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// Status and evidence recorded for one checklist item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemAssessment {
+    pub item_id: String,
+    pub status: ComplianceStatus,
+    pub evidence: Vec<EvidenceDocument>,
+    pub notes: Option<String>,
+    pub assessed_by: Option<Uuid>,
+    pub assessed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterReadiness {
+    pub chapter: String,
+    pub name: String,
+    /// Percentage of applicable items met, 0-100
+    pub score: f64,
+    pub compliant_items: usize,
+    pub applicable_items: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccreditationGap {
+    pub item_id: String,
+    pub chapter: String,
+    pub description: String,
+    pub core: bool,
+    pub status: ComplianceStatus,
+    pub missing_evidence: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccreditationGapReport {
+    pub program: AccreditationProgram,
+    pub edition: String,
+    pub generated_at: DateTime<Utc>,
+    pub overall_score: f64,
+    /// False while any core item is unmet, whatever the score
+    pub core_items_met: bool,
+    pub chapters: Vec<ChapterReadiness>,
+    pub gaps: Vec<AccreditationGap>,
+}
+
+impl AccreditationGapReport {
+    /// CSV export for sharing with the accreditation coordinator
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("item_id,chapter,core,status,missing_evidence,description\n");
+        for gap in &self.gaps {
+            csv.push_str(&format!(
+                "{},{},{},{:?},{},\"{}\"\n",
+                gap.item_id,
+                gap.chapter,
+                gap.core,
+                gap.status,
+                gap.missing_evidence,
+                gap.description.replace('"', "\"\"")
+            ));
+        }
+        csv
+    }
+}
+
+/// Tracks a hospital's progress against an accreditation checklist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccreditationTracker {
+    pub checklist: AccreditationChecklist,
+    assessments: HashMap<String, ItemAssessment>,
+}
+
+impl AccreditationTracker {
+    pub fn new(checklist: AccreditationChecklist) -> Self {
+        Self {
+            checklist,
+            assessments: HashMap::new(),
+        }
+    }
+
+    pub fn record_status(
+        &mut self,
+        item_id: &str,
+        status: ComplianceStatus,
+        assessed_by: Uuid,
+        notes: Option<String>,
+    ) -> Result<&ItemAssessment, HimsError> {
+        let assessment = self.assessment_mut(item_id)?;
+        assessment.status = status;
+        assessment.notes = notes;
+        assessment.assessed_by = Some(assessed_by);
+        assessment.assessed_at = Some(Utc::now());
+        Ok(assessment)
+    }
+
+    pub fn attach_evidence(
+        &mut self,
+        item_id: &str,
+        title: &str,
+        uri: &str,
+        uploaded_by: Uuid,
+    ) -> Result<EvidenceDocument, HimsError> {
+        let document = EvidenceDocument {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            uri: uri.to_string(),
+            uploaded_by,
+            uploaded_at: Utc::now(),
+        };
+        let assessment = self.assessment_mut(item_id)?;
+        if assessment.status == ComplianceStatus::NotStarted {
+            assessment.status = ComplianceStatus::InProgress;
+        }
+        assessment.evidence.push(document.clone());
+        Ok(document)
+    }
+
+    pub fn remove_evidence(&mut self, item_id: &str, document_id: Uuid) -> Result<(), HimsError> {
+        let assessment = self.assessment_mut(item_id)?;
+        assessment.evidence.retain(|document| document.id != document_id);
+        Ok(())
+    }
+
+    pub fn assessment(&self, item_id: &str) -> Option<&ItemAssessment> {
+        self.assessments.get(item_id)
+    }
+
+    pub fn status(&self, item_id: &str) -> ComplianceStatus {
+        self.assessments
+            .get(item_id)
+            .map(|assessment| assessment.status)
+            .unwrap_or(ComplianceStatus::NotStarted)
+    }
+
+    pub fn chapter_readiness(&self, chapter: &Chapter) -> ChapterReadiness {
+        let items = self.checklist.chapter_items(&chapter.code);
+        let (earned, applicable) = self.score_items(&items);
+        let compliant_items = items
+            .iter()
+            .filter(|item| self.item_credit(item) == 1.0)
+            .count();
+
+        ChapterReadiness {
+            chapter: chapter.code.clone(),
+            name: chapter.name.clone(),
+            score: percentage(earned, applicable),
+            compliant_items,
+            applicable_items: applicable,
+        }
+    }
+
+    /// Overall readiness, 0-100
+    pub fn readiness_score(&self) -> f64 {
+        let items: Vec<&ChecklistItem> = self.checklist.items.iter().collect();
+        let (earned, applicable) = self.score_items(&items);
+        percentage(earned, applicable)
+    }
+
+    pub fn gap_report(&self) -> AccreditationGapReport {
+        let gaps: Vec<AccreditationGap> = self
+            .checklist
+            .items
+            .iter()
+            .filter(|item| self.status(&item.id) != ComplianceStatus::NotApplicable)
+            .filter(|item| self.item_credit(item) < 1.0)
+            .map(|item| AccreditationGap {
+                item_id: item.id.clone(),
+                chapter: item.chapter.clone(),
+                description: item.description.clone(),
+                core: item.core,
+                status: self.status(&item.id),
+                missing_evidence: !self.has_evidence(&item.id),
+            })
+            .collect();
+
+        AccreditationGapReport {
+            program: self.checklist.program,
+            edition: self.checklist.edition.clone(),
+            generated_at: Utc::now(),
+            overall_score: self.readiness_score(),
+            core_items_met: !gaps.iter().any(|gap| gap.core),
+            chapters: self
+                .checklist
+                .chapters
+                .iter()
+                .map(|chapter| self.chapter_readiness(chapter))
+                .collect(),
+            gaps,
+        }
+    }
+
+    fn assessment_mut(&mut self, item_id: &str) -> Result<&mut ItemAssessment, HimsError> {
+        if self.checklist.get_item(item_id).is_none() {
+            return Err(HimsError::ValidationError {
+                message: format!("Unknown accreditation checklist item: {}", item_id),
+            });
+        }
+        Ok(self
+            .assessments
+            .entry(item_id.to_string())
+            .or_insert_with(|| ItemAssessment {
+                item_id: item_id.to_string(),
+                status: ComplianceStatus::NotStarted,
+                evidence: Vec::new(),
+                notes: None,
+                assessed_by: None,
+                assessed_at: None,
+            }))
+    }
+
+    fn has_evidence(&self, item_id: &str) -> bool {
+        self.assessments
+            .get(item_id)
+            .map(|assessment| !assessment.evidence.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// Credit towards the score: compliant items only count in full when
+    /// evidence backs them up
+    fn item_credit(&self, item: &ChecklistItem) -> f64 {
+        let evidence_backed = item.evidence_required.is_empty() || self.has_evidence(&item.id);
+        match self.status(&item.id) {
+            ComplianceStatus::Compliant if evidence_backed => 1.0,
+            ComplianceStatus::Compliant | ComplianceStatus::PartiallyCompliant => 0.5,
+            _ => 0.0,
+        }
+    }
+
+    fn score_items(&self, items: &[&ChecklistItem]) -> (f64, usize) {
+        let applicable: Vec<&&ChecklistItem> = items
+            .iter()
+            .filter(|item| self.status(&item.id) != ComplianceStatus::NotApplicable)
+            .collect();
+        let earned = applicable.iter().map(|item| self.item_credit(item)).sum();
+        (earned, applicable.len())
+    }
+}
+
+fn percentage(earned: f64, applicable: usize) -> f64 {
+    if applicable == 0 {
+        return 100.0;
+    }
+    (earned / applicable as f64 * 1000.0).round() / 10.0
+}
+
+fn chapters(definitions: &[(&str, &str)]) -> Vec<Chapter> {
+    definitions
+        .iter()
+        .map(|(code, name)| Chapter {
+            code: code.to_string(),
+            name: name.to_string(),
+        })
+        .collect()
+}
+
+fn item(id: &str, chapter: &str, description: &str, core: bool, evidence_required: &[&str]) -> ChecklistItem {
+    ChecklistItem {
+        id: id.to_string(),
+        chapter: chapter.to_string(),
+        description: description.to_string(),
+        core,
+        evidence_required: evidence_required.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checklist() -> AccreditationChecklist {
+        AccreditationChecklist {
+            program: AccreditationProgram::Nabh,
+            edition: "test edition".to_string(),
+            chapters: chapters(&[("AAC", "Access"), ("IMS", "Information"), ("HRM", "Human Resources")]),
+            items: vec![
+                item("AAC.1", "AAC", "Scope of services is displayed", true, &["Scope document"]),
+                item("AAC.2", "AAC", "Registration follows the \"front desk\" SOP", false, &["Registration SOP"]),
+                item("AAC.3", "AAC", "Signage is bilingual", false, &[]),
+                item("IMS.1", "IMS", "Medical records are maintained", true, &["Record audit"]),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_builtin_checklists() {
+        for checklist in [AccreditationChecklist::nabh(), AccreditationChecklist::jci()] {
+            assert!(!checklist.items.is_empty());
+            for item in &checklist.items {
+                assert!(checklist.chapters.iter().any(|chapter| chapter.code == item.chapter), "{}", item.id);
+                assert!(!item.evidence_required.is_empty(), "{}", item.id);
+            }
+        }
+        let nabh = AccreditationChecklist::nabh();
+        assert!(nabh.get_item("IMS.6").unwrap().core);
+        assert_eq!(nabh.chapter_items("MOM").len(), 3);
+        assert!(nabh.get_item("IPSG.1").is_none());
+    }
+
+    #[test]
+    fn test_evidence_tracking() {
+        let mut tracker = AccreditationTracker::new(checklist());
+        let uploader = Uuid::new_v4();
+        assert_eq!(tracker.status("AAC.1"), ComplianceStatus::NotStarted);
+
+        // Evidence starts work on an item but does not complete it
+        let document = tracker.attach_evidence("AAC.1", "Scope document", "docs/scope.pdf", uploader).unwrap();
+        assert_eq!(tracker.status("AAC.1"), ComplianceStatus::InProgress);
+        tracker.record_status("AAC.1", ComplianceStatus::Compliant, uploader, None).unwrap();
+        tracker.attach_evidence("AAC.1", "Scope photo", "docs/scope.jpg", uploader).unwrap();
+        assert_eq!(tracker.status("AAC.1"), ComplianceStatus::Compliant);
+        assert_eq!(tracker.assessment("AAC.1").unwrap().evidence.len(), 2);
+        assert_eq!(tracker.assessment("AAC.1").unwrap().assessed_by, Some(uploader));
+
+        tracker.remove_evidence("AAC.1", document.id).unwrap();
+        assert_eq!(tracker.assessment("AAC.1").unwrap().evidence[0].title, "Scope photo");
+
+        assert!(matches!(
+            tracker.attach_evidence("XYZ.9", "Unknown", "docs/x.pdf", uploader),
+            Err(HimsError::ValidationError { .. })
+        ));
+        assert!(tracker.record_status("XYZ.9", ComplianceStatus::Compliant, uploader, None).is_err());
+        assert!(tracker.assessment("XYZ.9").is_none());
+    }
+
+    #[test]
+    fn test_readiness_scores() {
+        let mut tracker = AccreditationTracker::new(checklist());
+        let assessor = Uuid::new_v4();
+        assert_eq!(tracker.readiness_score(), 0.0);
+
+        tracker.attach_evidence("AAC.1", "Scope document", "docs/scope.pdf", assessor).unwrap();
+        tracker.record_status("AAC.1", ComplianceStatus::Compliant, assessor, None).unwrap();
+        // Compliant without the required evidence only earns half credit
+        tracker.record_status("AAC.2", ComplianceStatus::Compliant, assessor, None).unwrap();
+        tracker.record_status("AAC.3", ComplianceStatus::NotApplicable, assessor, None).unwrap();
+        tracker.attach_evidence("IMS.1", "Record audit", "docs/audit.xlsx", assessor).unwrap();
+        tracker.record_status("IMS.1", ComplianceStatus::PartiallyCompliant, assessor, None).unwrap();
+
+        // (1 + 0.5 + 0.5) of 3 applicable items
+        assert_eq!(tracker.readiness_score(), 66.7);
+        let chapters = &checklist().chapters;
+        let access = tracker.chapter_readiness(&chapters[0]);
+        assert_eq!(access.score, 75.0);
+        assert_eq!(access.compliant_items, 1);
+        assert_eq!(access.applicable_items, 2);
+        assert_eq!(tracker.chapter_readiness(&chapters[1]).score, 50.0);
+        // A chapter with nothing applicable is not held against the hospital
+        assert_eq!(tracker.chapter_readiness(&chapters[2]).score, 100.0);
+    }
+
+    #[test]
+    fn test_gap_report() {
+        let mut tracker = AccreditationTracker::new(checklist());
+        let assessor = Uuid::new_v4();
+        tracker.attach_evidence("AAC.1", "Scope document", "docs/scope.pdf", assessor).unwrap();
+        tracker.record_status("AAC.1", ComplianceStatus::Compliant, assessor, None).unwrap();
+        tracker.record_status("AAC.2", ComplianceStatus::Compliant, assessor, None).unwrap();
+        tracker.record_status("AAC.3", ComplianceStatus::NotApplicable, assessor, None).unwrap();
+
+        let report = tracker.gap_report();
+        assert_eq!(report.program, AccreditationProgram::Nabh);
+        assert_eq!(report.chapters.len(), 3);
+        let gaps: Vec<&str> = report.gaps.iter().map(|gap| gap.item_id.as_str()).collect();
+        assert_eq!(gaps, vec!["AAC.2", "IMS.1"]);
+        assert!(report.gaps[0].missing_evidence);
+        assert_eq!(report.gaps[1].status, ComplianceStatus::NotStarted);
+        assert!(!report.core_items_met);
+
+        assert_eq!(
+            report.to_csv(),
+            "item_id,chapter,core,status,missing_evidence,description\n\
+             AAC.2,AAC,false,Compliant,true,\"Registration follows the \"\"front desk\"\" SOP\"\n\
+             IMS.1,IMS,true,NotStarted,true,\"Medical records are maintained\"\n"
+        );
+
+        // Closing the core gap leaves only the non-core one
+        tracker.attach_evidence("IMS.1", "Record audit", "docs/audit.xlsx", assessor).unwrap();
+        tracker.record_status("IMS.1", ComplianceStatus::Compliant, assessor, None).unwrap();
+        let report = tracker.gap_report();
+        assert_eq!(report.gaps.len(), 1);
+        assert!(report.core_items_met);
+    }
+}
@@ -1,42 +1,460 @@
-// Placeholder module implementations
+//! DICOM Part 10 files
+//!
+//! Reads the header of a DICOM file: the file meta information and the
+//! dataset up to the pixel data, in the implicit and explicit VR little
+//! endian transfer syntaxes and the compressed syntaxes built on the
+//! latter. Values of the string VRs are kept; sequences and binary values
+//! are skipped. Only the header is read, so a file may be sent without its
+//! pixel data.
 
-pub mod dicom {
-    pub struct DicomParser;
-    impl DicomParser {
-        pub fn parse_metadata(_file_path: &str) -> Result<String, crate::core::HimsError> {
-            Ok("{}".to_string()) // Placeholder
-        }
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::core::HimsError;
+
+/// A data element's (group, element) tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tag(pub u16, pub u16);
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:04X},{:04X})", self.0, self.1)
     }
 }
 
-pub mod terminology {
-    pub struct TerminologyService;
-    impl TerminologyService {
-        pub fn lookup_code(_system: &str, _code: &str) -> Result<String, crate::core::HimsError> {
-            Ok("Code found".to_string()) // Placeholder
-        }
+/// Tags of the attributes read and written here
+pub mod tags {
+    use super::Tag;
+
+    pub const TRANSFER_SYNTAX_UID: Tag = Tag(0x0002, 0x0010);
+    pub const SOP_CLASS_UID: Tag = Tag(0x0008, 0x0016);
+    pub const SOP_INSTANCE_UID: Tag = Tag(0x0008, 0x0018);
+    pub const STUDY_DATE: Tag = Tag(0x0008, 0x0020);
+    pub const STUDY_TIME: Tag = Tag(0x0008, 0x0030);
+    pub const ACCESSION_NUMBER: Tag = Tag(0x0008, 0x0050);
+    pub const MODALITY: Tag = Tag(0x0008, 0x0060);
+    pub const REFERRING_PHYSICIAN_NAME: Tag = Tag(0x0008, 0x0090);
+    pub const TIMEZONE_OFFSET_FROM_UTC: Tag = Tag(0x0008, 0x0201);
+    pub const STUDY_DESCRIPTION: Tag = Tag(0x0008, 0x1030);
+    pub const PATIENT_NAME: Tag = Tag(0x0010, 0x0010);
+    pub const PATIENT_ID: Tag = Tag(0x0010, 0x0020);
+    pub const PATIENT_BIRTH_DATE: Tag = Tag(0x0010, 0x0030);
+    pub const PATIENT_SEX: Tag = Tag(0x0010, 0x0040);
+    pub const STUDY_INSTANCE_UID: Tag = Tag(0x0020, 0x000D);
+    pub const SERIES_INSTANCE_UID: Tag = Tag(0x0020, 0x000E);
+    pub const REQUESTED_PROCEDURE_DESCRIPTION: Tag = Tag(0x0032, 0x1060);
+    pub const SCHEDULED_STATION_AE_TITLE: Tag = Tag(0x0040, 0x0001);
+    pub const SCHEDULED_PROCEDURE_STEP_START_DATE: Tag = Tag(0x0040, 0x0002);
+    pub const SCHEDULED_PROCEDURE_STEP_START_TIME: Tag = Tag(0x0040, 0x0003);
+    pub const SCHEDULED_PROCEDURE_STEP_DESCRIPTION: Tag = Tag(0x0040, 0x0007);
+    pub const SCHEDULED_PROCEDURE_STEP_ID: Tag = Tag(0x0040, 0x0009);
+    pub const SCHEDULED_PROCEDURE_STEP_STATUS: Tag = Tag(0x0040, 0x0020);
+    pub const SCHEDULED_PROCEDURE_STEP_SEQUENCE: Tag = Tag(0x0040, 0x0100);
+    pub const REQUESTED_PROCEDURE_ID: Tag = Tag(0x0040, 0x1001);
+    pub const REQUESTED_PROCEDURE_PRIORITY: Tag = Tag(0x0040, 0x1003);
+    pub const PIXEL_DATA: Tag = Tag(0x7FE0, 0x0010);
+}
+
+use tags::*;
+
+const ITEM: Tag = Tag(0xFFFE, 0xE000);
+const ITEM_DELIMITATION: Tag = Tag(0xFFFE, 0xE00D);
+const SEQUENCE_DELIMITATION: Tag = Tag(0xFFFE, 0xE0DD);
+const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+
+const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+const EXPLICIT_VR_BIG_ENDIAN: &str = "1.2.840.10008.1.2.2";
+const DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1.99";
+
+/// Sequences nest no deeper than this in the files we read
+const MAX_NESTING: usize = 32;
+
+/// VRs whose values are text
+const STRING_VRS: &[&[u8; 2]] = &[
+    b"AE", b"AS", b"CS", b"DA", b"DS", b"DT", b"IS", b"LO", b"LT", b"PN", b"SH", b"ST", b"TM", b"UC", b"UI",
+    b"UR", b"UT",
+];
+
+/// VRs with a 32-bit length after two reserved bytes in explicit VR
+const LONG_VRS: &[&[u8; 2]] = &[b"OB", b"OD", b"OF", b"OL", b"OV", b"OW", b"SQ", b"SV", b"UC", b"UN", b"UR", b"UT", b"UV"];
+
+/// String tags read from implicit VR files, which do not say their VR
+const IMPLICIT_STRING_TAGS: &[Tag] = &[
+    SOP_CLASS_UID,
+    SOP_INSTANCE_UID,
+    STUDY_DATE,
+    STUDY_TIME,
+    ACCESSION_NUMBER,
+    MODALITY,
+    REFERRING_PHYSICIAN_NAME,
+    TIMEZONE_OFFSET_FROM_UTC,
+    STUDY_DESCRIPTION,
+    PATIENT_NAME,
+    PATIENT_ID,
+    PATIENT_BIRTH_DATE,
+    PATIENT_SEX,
+    STUDY_INSTANCE_UID,
+    SERIES_INSTANCE_UID,
+    REQUESTED_PROCEDURE_DESCRIPTION,
+    REQUESTED_PROCEDURE_ID,
+];
+
+fn dicom_error(message: impl Into<String>) -> HimsError {
+    HimsError::DicomError { message: message.into() }
+}
+
+/// A DICOM UID derived from a random UUID, under the `2.25` root
+pub fn generate_uid() -> String {
+    format!("2.25.{}", uuid::Uuid::new_v4().as_u128())
+}
+
+/// String values of a DICOM file's header, by tag
+#[derive(Debug, Clone, Default)]
+pub struct DicomDataset {
+    elements: BTreeMap<Tag, String>,
+}
+
+impl DicomDataset {
+    /// The value of `tag`, all its values joined by `\`, with padding
+    /// trimmed; `None` if absent or empty
+    pub fn string(&self, tag: Tag) -> Option<&str> {
+        self.elements.get(&tag).map(String::as_str).filter(|value| !value.is_empty())
     }
+
+    pub fn transfer_syntax(&self) -> Option<&str> {
+        self.string(TRANSFER_SYNTAX_UID)
+    }
+}
+
+struct Header {
+    tag: Tag,
+    vr: Option<[u8; 2]>,
+    length: u32,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    explicit: bool,
 }
 
-pub mod abdm {
-    pub struct AbdmService;
-    impl AbdmService {
-        pub fn initiate_consent(_request: &str) -> Result<String, crate::core::HimsError> {
-            Ok("Consent initiated".to_string()) // Placeholder
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], HimsError> {
+        let end = self.position.checked_add(count).filter(|end| *end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err(dicom_error(format!("File ends inside an element at byte {}", self.position)));
+        };
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, HimsError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, HimsError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn peek_group(&self) -> Option<u16> {
+        self.bytes
+            .get(self.position..self.position + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn header(&mut self) -> Result<Header, HimsError> {
+        let tag = Tag(self.u16()?, self.u16()?);
+        // Items and delimiters carry no VR in either syntax
+        if tag.0 == 0xFFFE || !self.explicit {
+            return Ok(Header { tag, vr: None, length: self.u32()? });
         }
+        let vr = self.take(2)?;
+        let vr = [vr[0], vr[1]];
+        let length = match LONG_VRS.contains(&&vr) {
+            true => {
+                self.take(2)?;
+                self.u32()?
+            }
+            false => u32::from(self.u16()?),
+        };
+        Ok(Header { tag, vr: Some(vr), length })
+    }
+
+    fn skip(&mut self, length: u32, depth: usize) -> Result<(), HimsError> {
+        match length {
+            UNDEFINED_LENGTH => self.skip_items(depth + 1),
+            length => self.take(length as usize).map(|_| ()),
+        }
+    }
+
+    /// Skip the items of a sequence of undefined length, or of encapsulated
+    /// data, through its delimiter
+    fn skip_items(&mut self, depth: usize) -> Result<(), HimsError> {
+        if depth > MAX_NESTING {
+            return Err(dicom_error("Sequences are nested too deeply"));
+        }
+        loop {
+            let header = self.header()?;
+            match header.tag {
+                SEQUENCE_DELIMITATION => return Ok(()),
+                ITEM if header.length == UNDEFINED_LENGTH => loop {
+                    let element = self.header()?;
+                    if element.tag == ITEM_DELIMITATION {
+                        break;
+                    }
+                    self.skip(element.length, depth)?;
+                },
+                ITEM => self.skip(header.length, depth)?,
+                tag => return Err(dicom_error(format!("Expected an item in a sequence, found {}", tag))),
+            }
+        }
+    }
+
+    /// Read elements into `dataset` while `more` holds for the next group
+    fn read_into(&mut self, dataset: &mut DicomDataset, more: impl Fn(u16) -> bool) -> Result<(), HimsError> {
+        while !self.at_end() && self.peek_group().is_some_and(&more) {
+            let header = self.header()?;
+            if header.tag >= PIXEL_DATA {
+                break;
+            }
+            if header.length == UNDEFINED_LENGTH {
+                self.skip_items(1)?;
+                continue;
+            }
+            let value = self.take(header.length as usize)?;
+            let is_string = match header.vr {
+                Some(vr) => STRING_VRS.contains(&&vr),
+                None => IMPLICIT_STRING_TAGS.contains(&header.tag),
+            };
+            if is_string {
+                let text = String::from_utf8_lossy(value);
+                let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+                dataset.elements.insert(header.tag, text.to_string());
+            }
+        }
+        Ok(())
     }
 }
 
-pub mod accreditation {
-    pub struct AccreditationService;
-    impl AccreditationService {
-        pub fn validate_jci_compliance(_data: &str) -> Result<bool, crate::core::HimsError> {
-            Ok(true) // Placeholder
+/// DICOM file parser
+pub struct DicomParser;
+
+impl DicomParser {
+    /// Parse a DICOM Part 10 file's header
+    pub fn parse(bytes: &[u8]) -> Result<DicomDataset, HimsError> {
+        if bytes.get(128..132) != Some(&b"DICM"[..]) {
+            return Err(dicom_error("Not a DICOM Part 10 file: no DICM prefix"));
         }
+        let mut dataset = DicomDataset::default();
+        let mut reader = Reader {
+            bytes,
+            position: 132,
+            explicit: true,
+        };
+        reader.read_into(&mut dataset, |group| group == 0x0002)?;
+
+        match dataset.transfer_syntax() {
+            None => return Err(dicom_error("The file meta information has no transfer syntax")),
+            Some(EXPLICIT_VR_BIG_ENDIAN) => return Err(dicom_error("Explicit VR big endian files are not supported")),
+            Some(DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN) => return Err(dicom_error("Deflated files are not supported")),
+            Some(syntax) => reader.explicit = syntax != IMPLICIT_VR_LITTLE_ENDIAN,
+        }
+        reader.read_into(&mut dataset, |_| true)?;
+        Ok(dataset)
+    }
+
+    /// The study, series and patient of the DICOM file at `file_path`, as
+    /// JSON
+    pub fn parse_metadata(file_path: &str) -> Result<String, HimsError> {
+        let bytes = std::fs::read(file_path).map_err(|e| dicom_error(format!("Cannot read {}: {}", file_path, e)))?;
+        let instance = DicomInstance::from_dataset(&Self::parse(&bytes)?)?;
+        serde_json::to_string(&instance).map_err(|e| dicom_error(e.to_string()))
     }
 }
 
-pub use dicom::*;
-pub use terminology::*;
-pub use abdm::*;
-pub use accreditation::*;
\ No newline at end of file
+/// The study, series and patient a DICOM instance belongs to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DicomInstance {
+    pub study_instance_uid: String,
+    pub series_instance_uid: Option<String>,
+    pub sop_instance_uid: Option<String>,
+    pub sop_class_uid: Option<String>,
+    pub accession_number: Option<String>,
+    pub patient_id: Option<String>,
+    /// Person name, `Family^Given^Middle^Prefix^Suffix`
+    pub patient_name: Option<String>,
+    pub modality: Option<String>,
+    /// Study date and time, in the modality's local time
+    pub study_date_time: Option<NaiveDateTime>,
+    pub study_description: Option<String>,
+    pub referring_physician_name: Option<String>,
+}
+
+impl DicomInstance {
+    /// Read an instance's identification from its header; every instance
+    /// has a study instance UID
+    pub fn from_dataset(dataset: &DicomDataset) -> Result<Self, HimsError> {
+        let string = |tag: Tag| dataset.string(tag).map(str::to_string);
+        let Some(study_instance_uid) = string(STUDY_INSTANCE_UID) else {
+            return Err(dicom_error(format!("The file has no Study Instance UID {}", STUDY_INSTANCE_UID)));
+        };
+        Ok(Self {
+            study_instance_uid,
+            series_instance_uid: string(SERIES_INSTANCE_UID),
+            sop_instance_uid: string(SOP_INSTANCE_UID),
+            sop_class_uid: string(SOP_CLASS_UID),
+            accession_number: string(ACCESSION_NUMBER),
+            patient_id: string(PATIENT_ID),
+            patient_name: string(PATIENT_NAME),
+            modality: string(MODALITY),
+            study_date_time: dataset.string(STUDY_DATE).and_then(|date| {
+                parse_date_time(date, dataset.string(STUDY_TIME).unwrap_or_default())
+            }),
+            study_description: string(STUDY_DESCRIPTION),
+            referring_physician_name: string(REFERRING_PHYSICIAN_NAME),
+        })
+    }
+}
+
+/// A DA value and a TM value, `HH[MM[SS[.FFFFFF]]]`, which may be empty
+fn parse_date_time(date: &str, time: &str) -> Option<NaiveDateTime> {
+    let date = NaiveDate::parse_from_str(date, "%Y%m%d").ok()?;
+    let digits = time.split('.').next().unwrap_or_default();
+    let field = |range: std::ops::Range<usize>| digits.get(range).and_then(|field| field.parse().ok()).unwrap_or(0);
+    let time = NaiveTime::from_hms_opt(field(0..2), field(2..4), field(4..6))?;
+    Some(date.and_time(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(explicit: bool, tag: Tag, vr: &[u8; 2], value: &[u8]) -> Vec<u8> {
+        let mut value = value.to_vec();
+        if value.len() % 2 == 1 {
+            value.push(if vr == b"UI" { 0 } else { b' ' });
+        }
+        let mut bytes = [tag.0.to_le_bytes(), tag.1.to_le_bytes()].concat();
+        match (explicit, LONG_VRS.contains(&vr)) {
+            (true, true) => {
+                bytes.extend_from_slice(vr);
+                bytes.extend_from_slice(&[0, 0]);
+                bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            }
+            (true, false) => {
+                bytes.extend_from_slice(vr);
+                bytes.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            }
+            (false, _) => bytes.extend_from_slice(&(value.len() as u32).to_le_bytes()),
+        }
+        bytes.extend(value);
+        bytes
+    }
+
+    fn undefined(explicit: bool, tag: Tag, vr: &[u8; 2], contents: &[u8]) -> Vec<u8> {
+        let mut bytes = [tag.0.to_le_bytes(), tag.1.to_le_bytes()].concat();
+        if explicit {
+            bytes.extend_from_slice(vr);
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes.extend_from_slice(&UNDEFINED_LENGTH.to_le_bytes());
+        bytes.extend_from_slice(contents);
+        bytes
+    }
+
+    fn delimiter(tag: Tag) -> Vec<u8> {
+        [tag.0.to_le_bytes(), tag.1.to_le_bytes()].concat().into_iter().chain([0; 4]).collect()
+    }
+
+    fn file(transfer_syntax: &str, dataset: Vec<u8>) -> Vec<u8> {
+        let mut bytes = vec![0u8; 128];
+        bytes.extend_from_slice(b"DICM");
+        bytes.extend(element(true, Tag(0x0002, 0x0001), b"OB", &[0, 1]));
+        bytes.extend(element(true, TRANSFER_SYNTAX_UID, b"UI", transfer_syntax.as_bytes()));
+        bytes.extend(dataset);
+        bytes
+    }
+
+    fn dataset(explicit: bool) -> Vec<u8> {
+        // A sequence of undefined length holding an item of undefined
+        // length, holding another such sequence
+        let inner = [
+            undefined(explicit, Tag(0x0008, 0x1250), b"SQ", &delimiter(SEQUENCE_DELIMITATION)),
+            element(explicit, Tag(0x0008, 0x1150), b"UI", b"1.2.3"),
+            delimiter(ITEM_DELIMITATION),
+        ]
+        .concat();
+        let item = undefined(false, ITEM, b"  ", &inner);
+        [
+            element(explicit, STUDY_DATE, b"DA", b"20240506"),
+            element(explicit, STUDY_TIME, b"TM", b"143005.25"),
+            element(explicit, ACCESSION_NUMBER, b"SH", b"A000000042"),
+            element(explicit, MODALITY, b"CS", b"CT"),
+            undefined(explicit, Tag(0x0008, 0x1110), b"SQ", &[item, delimiter(SEQUENCE_DELIMITATION)].concat()),
+            element(explicit, PATIENT_NAME, b"PN", b"Doe^Jane"),
+            element(explicit, PATIENT_ID, b"LO", b"MRN-1"),
+            element(explicit, Tag(0x0018, 0x0050), b"DS", b"1.25"),
+            element(explicit, Tag(0x0018, 0x9999), b"OB", &[1, 2, 3, 4]),
+            element(explicit, STUDY_INSTANCE_UID, b"UI", b"2.25.1234"),
+            element(explicit, SERIES_INSTANCE_UID, b"UI", b"2.25.5678"),
+            undefined(explicit, PIXEL_DATA, b"OB", &[0xFF; 10]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_parse_explicit_vr() {
+        let dataset = DicomParser::parse(&file("1.2.840.10008.1.2.4.50", dataset(true))).unwrap();
+        assert_eq!(dataset.transfer_syntax(), Some("1.2.840.10008.1.2.4.50"));
+        assert_eq!(dataset.string(Tag(0x0018, 0x0050)), Some("1.25"));
+        assert_eq!(dataset.string(Tag(0x0008, 0x1150)), None, "nested in a sequence");
+
+        let instance = DicomInstance::from_dataset(&dataset).unwrap();
+        assert_eq!(instance.study_instance_uid, "2.25.1234");
+        assert_eq!(instance.series_instance_uid.as_deref(), Some("2.25.5678"));
+        assert_eq!(instance.accession_number.as_deref(), Some("A000000042"));
+        assert_eq!(instance.patient_id.as_deref(), Some("MRN-1"));
+        assert_eq!(instance.patient_name.as_deref(), Some("Doe^Jane"));
+        assert_eq!(instance.modality.as_deref(), Some("CT"));
+        assert_eq!(
+            instance.study_date_time,
+            NaiveDate::from_ymd_opt(2024, 5, 6).unwrap().and_hms_opt(14, 30, 5)
+        );
+    }
+
+    #[test]
+    fn test_parse_implicit_vr() {
+        let dataset = DicomParser::parse(&file(IMPLICIT_VR_LITTLE_ENDIAN, dataset(false))).unwrap();
+        let instance = DicomInstance::from_dataset(&dataset).unwrap();
+        assert_eq!(instance.study_instance_uid, "2.25.1234");
+        assert_eq!(instance.patient_id.as_deref(), Some("MRN-1"));
+        assert_eq!(dataset.string(Tag(0x0018, 0x0050)), None, "VR unknown without a dictionary entry");
+    }
+
+    #[test]
+    fn test_parse_rejects() {
+        assert!(DicomParser::parse(b"not dicom").is_err());
+        assert!(DicomParser::parse(&file(EXPLICIT_VR_BIG_ENDIAN, dataset(true))).is_err());
+
+        let mut truncated = file(IMPLICIT_VR_LITTLE_ENDIAN, dataset(false));
+        truncated.truncate(250);
+        assert!(DicomParser::parse(&truncated).is_err());
+
+        let no_study = file(IMPLICIT_VR_LITTLE_ENDIAN, element(false, PATIENT_ID, b"LO", b"MRN-1"));
+        let dataset = DicomParser::parse(&no_study).unwrap();
+        assert!(DicomInstance::from_dataset(&dataset).is_err());
+
+        assert_eq!(parse_date_time("20240506", ""), NaiveDate::from_ymd_opt(2024, 5, 6).unwrap().and_hms_opt(0, 0, 0));
+        assert_eq!(parse_date_time("20240506", "0915"), NaiveDate::from_ymd_opt(2024, 5, 6).unwrap().and_hms_opt(9, 15, 0));
+        assert!(generate_uid().starts_with("2.25."));
+    }
+}
@@ -1,9 +1,15 @@
 pub mod models;
+#[cfg(feature = "server")]
 pub mod client;
 pub mod transformers;
+pub mod profiles;
+pub mod packs;
 pub mod validators;
 
 pub use models::*;
+#[cfg(feature = "server")]
 pub use client::*;
 pub use transformers::*;
+pub use profiles::*;
+pub use packs::*;
 pub use validators::*;
\ No newline at end of file
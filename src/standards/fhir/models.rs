@@ -100,6 +100,12 @@ pub struct BundleEntry {
     pub full_url: Option<String>,
 }
 
+impl Default for Patient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Patient {
     pub fn new() -> Self {
         Self {
@@ -122,4 +128,103 @@ impl Patient {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+/// FHIR OperationOutcome resource: the result of validating or processing a
+/// resource, as a list of issues with severities
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationOutcome {
+    #[serde(rename = "resourceType", default = "OperationOutcome::resource_type")]
+    pub resource_type: String,
+    pub issue: Vec<OperationOutcomeIssue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationOutcomeIssue {
+    pub severity: IssueSeverity,
+    pub code: IssueType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<String>,
+    /// FHIRPath locations of the problem, e.g. `Patient.identifier[0].system`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expression: Vec<String>,
+}
+
+/// Issue severities, most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    Fatal,
+    Error,
+    Warning,
+    Information,
+}
+
+/// The subset of the FHIR issue-type codes the validators report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueType {
+    Invalid,
+    Structure,
+    Required,
+    Value,
+    CodeInvalid,
+    NotSupported,
+    NotFound,
+    Informational,
+}
+
+impl OperationOutcome {
+    pub fn new() -> Self {
+        Self {
+            resource_type: Self::resource_type(),
+            issue: Vec::new(),
+        }
+    }
+
+    fn resource_type() -> String {
+        "OperationOutcome".to_string()
+    }
+
+    pub fn push(
+        &mut self,
+        severity: IssueSeverity,
+        code: IssueType,
+        expression: impl Into<String>,
+        diagnostics: impl Into<String>,
+    ) {
+        self.issue.push(OperationOutcomeIssue {
+            severity,
+            code,
+            diagnostics: Some(diagnostics.into()),
+            expression: vec![expression.into()],
+        });
+    }
+
+    pub fn extend(&mut self, other: OperationOutcome) {
+        self.issue.extend(other.issue);
+    }
+
+    /// Valid when no issue is an error or fatal; warnings do not count
+    pub fn is_valid(&self) -> bool {
+        !self.issue.iter().any(|issue| issue.severity <= IssueSeverity::Error)
+    }
+
+    /// Issues at `severity` or worse
+    pub fn issues_at_least(&self, severity: IssueSeverity) -> impl Iterator<Item = &OperationOutcomeIssue> {
+        self.issue.iter().filter(move |issue| issue.severity <= severity)
+    }
+
+    /// An outcome that still says "all OK" when there is nothing to report,
+    /// as FHIR requires at least one issue
+    pub fn finish(mut self) -> Self {
+        if self.issue.is_empty() {
+            self.issue.push(OperationOutcomeIssue {
+                severity: IssueSeverity::Information,
+                code: IssueType::Informational,
+                diagnostics: Some("All OK".to_string()),
+                expression: Vec::new(),
+            });
+        }
+        self
+    }
+}
@@ -0,0 +1,241 @@
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::core::{HimsConfig, HimsError};
+use crate::standards::fhir::models::{IssueSeverity, OperationOutcome};
+use crate::standards::fhir::profiles::ProfileRegistry;
+use crate::standards::fhir::validators::ProfileValidator;
+
+/// US Core 6.1.0, trimmed to the profiles for resources this server stores
+/// and the terminology their required bindings use
+const US_CORE_PACKAGE: &str = include_str!("packages/hl7.fhir.us.core-6.1.0.subset.json");
+
+/// NRCES FHIR IG for ABDM 6.5.0, trimmed likewise
+const NDHM_PACKAGE: &str = include_str!("packages/ndhm.in-6.5.0.subset.json");
+
+/// National profile pack resources must conform to, chosen by the
+/// deployment's country
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilePack {
+    /// US Core, for the United States
+    UsCore,
+    /// ABDM profiles published by NRCES, for India
+    Ndhm,
+}
+
+impl ProfilePack {
+    pub const ALL: [ProfilePack; 2] = [ProfilePack::UsCore, ProfilePack::Ndhm];
+
+    /// Package id and version, as in a FHIR package registry
+    pub fn package_id(&self) -> &'static str {
+        match self {
+            ProfilePack::UsCore => "hl7.fhir.us.core#6.1.0",
+            ProfilePack::Ndhm => "ndhm.in#6.5.0",
+        }
+    }
+
+    /// Pack for an ISO 3166-1 alpha-2 country code; countries without one
+    /// validate against the base FHIR specification only
+    pub fn for_country(country_code: &str) -> Option<Self> {
+        match country_code.to_ascii_uppercase().as_str() {
+            "US" => Some(ProfilePack::UsCore),
+            "IN" => Some(ProfilePack::Ndhm),
+            _ => None,
+        }
+    }
+
+    /// Pack selected by `HimsConfig::country_code`
+    pub fn for_config(config: &HimsConfig) -> Option<Self> {
+        config.country_code.as_deref().and_then(Self::for_country)
+    }
+
+    /// Profile a resource type must validate against under this pack
+    pub fn profile_for(&self, resource_type: &str) -> Option<&'static str> {
+        match (self, resource_type) {
+            (ProfilePack::UsCore, "Patient") => Some("http://hl7.org/fhir/us/core/StructureDefinition/us-core-patient"),
+            (ProfilePack::UsCore, "DocumentReference") => {
+                Some("http://hl7.org/fhir/us/core/StructureDefinition/us-core-documentreference")
+            }
+            (ProfilePack::Ndhm, "Patient") => Some("https://nrces.in/ndhm/fhir/r4/StructureDefinition/Patient"),
+            (ProfilePack::Ndhm, "DocumentReference") => {
+                Some("https://nrces.in/ndhm/fhir/r4/StructureDefinition/DocumentReference")
+            }
+            _ => None,
+        }
+    }
+
+    /// Add the embedded package's profiles and terminology to a registry.
+    /// Returns how many resources were added.
+    pub fn load_into(&self, registry: &mut ProfileRegistry) -> Result<usize, HimsError> {
+        let package = match self {
+            ProfilePack::UsCore => US_CORE_PACKAGE,
+            ProfilePack::Ndhm => NDHM_PACKAGE,
+        };
+        let bundle: Value = serde_json::from_str(package).map_err(|e| HimsError::FhirError {
+            message: format!("Embedded package {} is not valid JSON: {}", self.package_id(), e),
+        })?;
+        registry.add_resource(bundle)
+    }
+
+    /// A registry holding just this pack
+    pub fn registry(&self) -> Result<ProfileRegistry, HimsError> {
+        let mut registry = ProfileRegistry::new();
+        self.load_into(&mut registry)?;
+        Ok(registry)
+    }
+}
+
+/// Checks resources the server writes against the deployment's profile pack
+#[derive(Debug, Clone)]
+pub struct PackValidator {
+    pack: ProfilePack,
+    registry: Arc<ProfileRegistry>,
+}
+
+impl PackValidator {
+    pub fn new(pack: ProfilePack) -> Result<Self, HimsError> {
+        Ok(Self {
+            pack,
+            registry: Arc::new(pack.registry()?),
+        })
+    }
+
+    /// Validator for the pack `HimsConfig::country_code` selects; `None`
+    /// when the country has no pack
+    pub fn for_config(config: &HimsConfig) -> Result<Option<Self>, HimsError> {
+        ProfilePack::for_config(config).map(Self::new).transpose()
+    }
+
+    pub fn pack(&self) -> ProfilePack {
+        self.pack
+    }
+
+    /// Validate a resource against the profile the pack requires for its type
+    pub fn validate(&self, resource: &Value) -> OperationOutcome {
+        ProfileValidator::new(&self.registry).validate_for_pack(resource, self.pack)
+    }
+
+    /// Fail with the outcome's errors when a resource does not conform
+    pub fn require(&self, resource: &Value) -> Result<(), HimsError> {
+        let outcome = self.validate(resource);
+        if outcome.is_valid() {
+            return Ok(());
+        }
+        let errors: Vec<String> = outcome
+            .issues_at_least(IssueSeverity::Error)
+            .map(|issue| match (issue.expression.first(), &issue.diagnostics) {
+                (Some(location), Some(diagnostics)) => format!("{}: {}", location, diagnostics),
+                (None, Some(diagnostics)) => diagnostics.clone(),
+                (location, None) => location.cloned().unwrap_or_default(),
+            })
+            .collect();
+        Err(HimsError::ValidationError {
+            message: format!("Does not conform to {}: {}", self.pack.package_id(), errors.join("; ")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        ContactPoint, ContactPointSystem, Gender, HumanName, Identifier, MedicalRecord, MedicalRecordType, Patient,
+        Reference, ABHA_NUMBER_SYSTEM,
+    };
+    use uuid::Uuid;
+
+    fn patient(identifier: Option<&str>) -> Patient {
+        let mut patient = Patient::new(
+            vec![HumanName {
+                use_type: None,
+                text: Some("Asha Rao".to_string()),
+                family: Some("Rao".to_string()),
+                given: vec!["Asha".to_string()],
+                prefix: vec![],
+                suffix: vec![],
+            }],
+            vec![ContactPoint {
+                system: ContactPointSystem::Phone,
+                value: "+91-9800000000".to_string(),
+                use_type: None,
+                rank: None,
+            }],
+            Gender::Female,
+            chrono::NaiveDate::from_ymd_opt(1984, 6, 1),
+        );
+        patient.identifier = identifier
+            .map(|system| Identifier {
+                use_type: Some("official".to_string()),
+                system: Some(system.to_string()),
+                value: "91-1234-5678-9012".to_string(),
+            })
+            .into_iter()
+            .collect();
+        patient
+    }
+
+    fn record() -> MedicalRecord {
+        let author = Reference {
+            reference: format!("Practitioner/{}", Uuid::new_v4()),
+            display: None,
+        };
+        MedicalRecord::new(Uuid::new_v4(), MedicalRecordType::ProgressNote, "Afebrile.".to_string(), author)
+    }
+
+    fn config(country_code: &str) -> HimsConfig {
+        HimsConfig {
+            country_code: Some(country_code.to_string()),
+            ..HimsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_pack_for_config() {
+        assert_eq!(PackValidator::for_config(&config("us")).unwrap().unwrap().pack(), ProfilePack::UsCore);
+        assert_eq!(PackValidator::for_config(&config("IN")).unwrap().unwrap().pack(), ProfilePack::Ndhm);
+        assert!(PackValidator::for_config(&config("KE")).unwrap().is_none());
+        assert!(PackValidator::for_config(&HimsConfig::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_us_core_patient() {
+        let validator = PackValidator::new(ProfilePack::UsCore).unwrap();
+        assert!(validator.require(&patient(Some("urn:oid:2.16.840.1.113883.19.5")).to_fhir()).is_ok());
+
+        let error = validator.require(&patient(None).to_fhir()).unwrap_err().to_string();
+        assert!(error.contains("hl7.fhir.us.core#6.1.0"), "{}", error);
+        assert!(error.contains("Patient.identifier"), "{}", error);
+    }
+
+    #[test]
+    fn test_ndhm_patient() {
+        let validator = PackValidator::new(ProfilePack::Ndhm).unwrap();
+        let abha = patient(Some(ABHA_NUMBER_SYSTEM)).to_fhir();
+        assert_eq!(abha.pointer("/identifier/0/type/coding/0/code").unwrap(), "ABHA");
+        assert!(validator.validate(&abha).is_valid());
+
+        let mut untitled = patient(Some(ABHA_NUMBER_SYSTEM));
+        untitled.name[0].text = None;
+        let error = validator.require(&untitled.to_fhir()).unwrap_err().to_string();
+        assert!(error.contains("Patient.name.text"), "{}", error);
+    }
+
+    #[test]
+    fn test_document_reference() {
+        let document = record().to_document_reference();
+        assert_eq!(document.pointer("/type/coding/0/code").unwrap(), "11506-3");
+        assert!(document.get("context").is_none());
+        for pack in ProfilePack::ALL {
+            let outcome = PackValidator::new(pack).unwrap().validate(&document);
+            assert!(outcome.is_valid(), "{}: {:?}", pack.package_id(), outcome);
+        }
+
+        let mut retracted = record();
+        retracted.mark_error();
+        assert_eq!(retracted.to_document_reference()["status"], "entered-in-error");
+
+        let mut unattributed = record().to_document_reference();
+        unattributed.as_object_mut().unwrap().remove("subject");
+        assert!(PackValidator::new(ProfilePack::UsCore).unwrap().require(&unattributed).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::core::HimsError;
+
+/// A FHIR StructureDefinition (profile), reduced to what validation reads
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureDefinition {
+    pub url: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// Resource or datatype the profile constrains, e.g. `Patient`
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub base_definition: Option<String>,
+    pub snapshot: Option<ElementList>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ElementList {
+    pub element: Vec<ElementDefinition>,
+}
+
+/// One element of a profile's snapshot
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementDefinition {
+    /// Unique within the profile, and names slices: `Patient.identifier:abha.system`
+    pub id: String,
+    pub path: String,
+    pub slice_name: Option<String>,
+    pub min: Option<u32>,
+    /// A number or `*`
+    pub max: Option<String>,
+    pub slicing: Option<Slicing>,
+    pub binding: Option<Binding>,
+    #[serde(rename = "type", default)]
+    pub types: Vec<ElementType>,
+    /// `fixed[x]` and `pattern[x]`, among the other choice properties
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ElementType {
+    pub code: String,
+    #[serde(default)]
+    pub profile: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Slicing {
+    #[serde(default)]
+    pub discriminator: Vec<Discriminator>,
+    /// `closed`, `open` or `openAtEnd`
+    pub rules: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Discriminator {
+    /// `value`, `pattern`, `exists`, `type` or `profile`
+    #[serde(rename = "type")]
+    pub discriminator_type: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Binding {
+    /// `required`, `extensible`, `preferred` or `example`
+    pub strength: String,
+    pub value_set: Option<String>,
+}
+
+/// Value an element is constrained to
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueConstraint<'a> {
+    /// `fixed[x]`: the value must be exactly this
+    Fixed(&'a Value),
+    /// `pattern[x]`: the value must contain at least this
+    Pattern(&'a Value),
+}
+
+impl ElementDefinition {
+    /// Last segment of the path: `system` for `Patient.identifier:abha.system`
+    pub fn name(&self) -> &str {
+        self.path.rsplit('.').next().unwrap_or(&self.path)
+    }
+
+    /// Whether the element is `value[x]` style, present in instances as
+    /// `valueQuantity`, `valueString`, ...
+    pub fn is_choice(&self) -> bool {
+        self.path.ends_with("[x]")
+    }
+
+    pub fn max_occurs(&self) -> Option<usize> {
+        match self.max.as_deref() {
+            None | Some("*") => None,
+            Some(max) => max.parse().ok(),
+        }
+    }
+
+    pub fn constraint(&self) -> Option<ValueConstraint<'_>> {
+        let find = |prefix: &str| {
+            self.extra
+                .iter()
+                .find(|(key, _)| key.strip_prefix(prefix).is_some_and(|rest| rest.starts_with(char::is_uppercase)))
+                .map(|(_, value)| value)
+        };
+        find("fixed")
+            .map(ValueConstraint::Fixed)
+            .or_else(|| find("pattern").map(ValueConstraint::Pattern))
+    }
+}
+
+impl StructureDefinition {
+    pub fn elements(&self) -> &[ElementDefinition] {
+        self.snapshot.as_ref().map(|snapshot| snapshot.element.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn element(&self, id: &str) -> Option<&ElementDefinition> {
+        self.elements().iter().find(|element| element.id == id)
+    }
+
+    /// Elements directly below `parent_id`, slices excluded
+    pub fn children<'a>(&'a self, parent_id: &'a str) -> impl Iterator<Item = &'a ElementDefinition> {
+        self.elements().iter().filter(move |element| {
+            element
+                .id
+                .strip_prefix(parent_id)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .is_some_and(|rest| !rest.contains('.') && !rest.contains(':'))
+        })
+    }
+
+    /// Slices of the element `id`, in declaration order
+    pub fn slices<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a ElementDefinition> {
+        self.elements().iter().filter(move |element| {
+            element
+                .id
+                .strip_prefix(id)
+                .and_then(|rest| rest.strip_prefix(':'))
+                .is_some_and(|rest| !rest.contains('.') && !rest.contains(':'))
+        })
+    }
+}
+
+/// A code as a ValueSet lists it. `system` is `None` for codes that stand
+/// alone, such as the members of an inline `code` binding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValueSetCode {
+    pub system: Option<String>,
+    pub code: String,
+}
+
+/// A code with its display, as a CodeSystem or ValueSet defines it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Concept {
+    pub code: String,
+    pub display: Option<String>,
+}
+
+/// Profiles and terminology loaded from downloaded FHIR packages (US Core,
+/// ABDM, ...), keyed by canonical URL
+#[derive(Debug, Clone, Default)]
+pub struct ProfileRegistry {
+    profiles: HashMap<String, StructureDefinition>,
+    value_sets: HashMap<String, Value>,
+    code_systems: HashMap<String, Vec<Concept>>,
+}
+
+impl ProfileRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every `*.json` conformance resource in a directory, such as the
+    /// `package/` folder of an unpacked npm FHIR package. Other resources
+    /// and files that are not FHIR JSON are skipped.
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize, HimsError> {
+        let entries = std::fs::read_dir(dir).map_err(|e| HimsError::ConfigurationError {
+            message: format!("Cannot read profile directory {}: {}", dir.display(), e),
+        })?;
+
+        let mut loaded = 0;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| HimsError::ConfigurationError { message: e.to_string() })?
+                .path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path).map_err(|e| HimsError::ConfigurationError {
+                message: format!("Cannot read {}: {}", path.display(), e),
+            })?;
+            let Ok(resource) = serde_json::from_str::<Value>(&contents) else {
+                tracing::warn!("Skipping {}: not JSON", path.display());
+                continue;
+            };
+            loaded += self.add_resource(resource)?;
+        }
+        Ok(loaded)
+    }
+
+    /// Add a StructureDefinition, ValueSet or CodeSystem, or every one of
+    /// them in a Bundle. Returns how many were added.
+    pub fn add_resource(&mut self, resource: Value) -> Result<usize, HimsError> {
+        match resource.get("resourceType").and_then(Value::as_str) {
+            Some("StructureDefinition") => {
+                let profile: StructureDefinition =
+                    serde_json::from_value(resource).map_err(|e| HimsError::FhirError {
+                        message: format!("Invalid StructureDefinition: {}", e),
+                    })?;
+                self.add_profile(profile);
+                Ok(1)
+            }
+            Some("ValueSet") => match canonical_url(&resource) {
+                Some(url) => {
+                    self.value_sets.insert(url, resource);
+                    Ok(1)
+                }
+                None => Ok(0),
+            },
+            Some("CodeSystem") => match canonical_url(&resource) {
+                Some(url) => {
+                    let mut codes = Vec::new();
+                    collect_concepts(resource.get("concept"), &mut codes);
+                    self.code_systems.insert(url, codes);
+                    Ok(1)
+                }
+                None => Ok(0),
+            },
+            Some("Bundle") => {
+                let entries = resource.get("entry").and_then(Value::as_array).cloned().unwrap_or_default();
+                let mut added = 0;
+                for entry in entries {
+                    if let Some(resource) = entry.get("resource") {
+                        added += self.add_resource(resource.clone())?;
+                    }
+                }
+                Ok(added)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    pub fn add_profile(&mut self, profile: StructureDefinition) {
+        self.profiles.insert(profile.url.clone(), profile);
+    }
+
+    /// Profile by canonical URL; a `|version` suffix is ignored
+    pub fn profile(&self, url: &str) -> Option<&StructureDefinition> {
+        self.profiles.get(strip_version(url))
+    }
+
+    pub fn profiles(&self) -> impl Iterator<Item = &StructureDefinition> {
+        self.profiles.values()
+    }
+
+    /// Codes in a ValueSet, from its expansion if the package ships one and
+    /// otherwise from its `compose`. `None` when the ValueSet, or a code
+    /// system it includes whole, is not loaded.
+    pub fn value_set_codes(&self, url: &str) -> Option<HashSet<ValueSetCode>> {
+        self.expand(strip_version(url), &mut Vec::new())
+    }
+
+    fn expand(&self, url: &str, seen: &mut Vec<String>) -> Option<HashSet<ValueSetCode>> {
+        // ValueSets may import each other; a cycle adds nothing
+        if seen.iter().any(|visited| visited == url) {
+            return Some(HashSet::new());
+        }
+        seen.push(url.to_string());
+
+        let value_set = self.value_sets.get(url)?;
+        let mut codes = HashSet::new();
+
+        if let Some(contains) = value_set.pointer("/expansion/contains") {
+            collect_expansion(contains, &mut codes);
+            return Some(codes);
+        }
+
+        let includes = value_set.pointer("/compose/include").and_then(Value::as_array)?;
+        for include in includes {
+            let system = include.get("system").and_then(Value::as_str);
+            match include.get("concept").and_then(Value::as_array) {
+                Some(concepts) => codes.extend(concepts.iter().filter_map(|concept| {
+                    Some(ValueSetCode {
+                        system: system.map(str::to_string),
+                        code: concept.get("code")?.as_str()?.to_string(),
+                    })
+                })),
+                None if include.get("filter").is_some() => {
+                    // Filters need a terminology server; the set is not known here
+                    return None;
+                }
+                None => {
+                    if let Some(system) = system {
+                        let whole = self.code_systems.get(strip_version(system))?;
+                        codes.extend(whole.iter().map(|concept| ValueSetCode {
+                            system: Some(system.to_string()),
+                            code: concept.code.clone(),
+                        }));
+                    }
+                }
+            }
+            for imported in include.get("valueSet").and_then(Value::as_array).into_iter().flatten() {
+                codes.extend(self.expand(strip_version(imported.as_str()?), seen)?);
+            }
+        }
+        Some(codes)
+    }
+
+    /// Whether a CodeSystem is loaded; a `|version` suffix is ignored
+    pub fn has_code_system(&self, url: &str) -> bool {
+        self.code_systems.contains_key(strip_version(url))
+    }
+
+    /// A code of a system, from the CodeSystem if it is loaded and otherwise
+    /// from any ValueSet listing it, which is how packages carry codes of
+    /// external systems such as LOINC. `None` when neither knows the code.
+    pub fn lookup_code(&self, system: &str, code: &str) -> Option<Concept> {
+        let system = strip_version(system);
+        if let Some(concepts) = self.code_systems.get(system) {
+            return concepts.iter().find(|concept| concept.code == code).cloned();
+        }
+        self.value_sets.values().find_map(|value_set| listed_concept(value_set, system, code))
+    }
+}
+
+fn canonical_url(resource: &Value) -> Option<String> {
+    resource.get("url").and_then(Value::as_str).map(str::to_string)
+}
+
+fn strip_version(url: &str) -> &str {
+    url.split('|').next().unwrap_or(url)
+}
+
+fn to_concept(item: &Value) -> Option<Concept> {
+    Some(Concept {
+        code: item.get("code")?.as_str()?.to_string(),
+        display: item.get("display").and_then(Value::as_str).map(str::to_string),
+    })
+}
+
+fn collect_concepts(concepts: Option<&Value>, codes: &mut Vec<Concept>) {
+    for concept in concepts.and_then(Value::as_array).into_iter().flatten() {
+        codes.extend(to_concept(concept));
+        collect_concepts(concept.get("concept"), codes);
+    }
+}
+
+/// A code of `system` listed in a ValueSet's expansion or compose
+fn listed_concept(value_set: &Value, system: &str, code: &str) -> Option<Concept> {
+    fn in_expansion(contains: &Value, system: &str, code: &str) -> Option<Concept> {
+        contains.as_array()?.iter().find_map(|item| {
+            let matches = item.get("system").and_then(Value::as_str).map(strip_version) == Some(system)
+                && item.get("code").and_then(Value::as_str) == Some(code);
+            if matches {
+                to_concept(item)
+            } else {
+                in_expansion(item.get("contains")?, system, code)
+            }
+        })
+    }
+
+    if let Some(concept) = value_set.pointer("/expansion/contains").and_then(|contains| in_expansion(contains, system, code)) {
+        return Some(concept);
+    }
+    value_set
+        .pointer("/compose/include")?
+        .as_array()?
+        .iter()
+        .filter(|include| include.get("system").and_then(Value::as_str).map(strip_version) == Some(system))
+        .filter_map(|include| include.get("concept")?.as_array())
+        .flatten()
+        .find(|concept| concept.get("code").and_then(Value::as_str) == Some(code))
+        .and_then(to_concept)
+}
+
+fn collect_expansion(contains: &Value, codes: &mut HashSet<ValueSetCode>) {
+    for item in contains.as_array().into_iter().flatten() {
+        if let Some(code) = item.get("code").and_then(Value::as_str) {
+            codes.insert(ValueSetCode {
+                system: item.get("system").and_then(Value::as_str).map(str::to_string),
+                code: code.to_string(),
+            });
+        }
+        if let Some(nested) = item.get("contains") {
+            collect_expansion(nested, codes);
+        }
+    }
+}
@@ -1,37 +1,17 @@
-// Placeholder implementations for remaining modules
-
 // FHIR Transformers
-pub mod transformers {
-    use crate::core::HimsError;
-    
-    pub struct FhirTransformer;
-    
-    impl FhirTransformer {
-        pub fn hl7_to_fhir(hl7_message: &str) -> Result<String, HimsError> {
-            // Transform HL7v2 to FHIR
-            Ok("{}".to_string()) // Placeholder
-        }
-        
-        pub fn csv_to_fhir(csv_data: &str) -> Result<String, HimsError> {
-            // Transform CSV to FHIR
-            Ok("{}".to_string()) // Placeholder
-        }
+
+use crate::core::HimsError;
+
+pub struct FhirTransformer;
+
+impl FhirTransformer {
+    pub fn hl7_to_fhir(_hl7_message: &str) -> Result<String, HimsError> {
+        // Transform HL7v2 to FHIR
+        Ok("{}".to_string()) // Placeholder
     }
-}
 
-// FHIR Validators
-pub mod validators {
-    use crate::core::HimsError;
-    use crate::standards::fhir::models::Patient;
-    
-    pub struct FhirValidator;
-    
-    impl FhirValidator {
-        pub fn validate_patient(patient: &Patient) -> Result<(), HimsError> {
-            patient.validate()
-        }
+    pub fn csv_to_fhir(_csv_data: &str) -> Result<String, HimsError> {
+        // Transform CSV to FHIR
+        Ok("{}".to_string()) // Placeholder
     }
 }
-
-pub use transformers::*;
-pub use validators::*;
\ No newline at end of file
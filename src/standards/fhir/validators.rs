@@ -1,5 +1,14 @@
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
 use crate::core::HimsError;
-use crate::standards::fhir::models::Patient;
+use crate::models::constants::UCUM_SYSTEM;
+use crate::standards::fhir::models::{IssueSeverity, IssueType, OperationOutcome, Patient};
+use crate::standards::fhir::packs::ProfilePack;
+use crate::standards::fhir::profiles::{
+    Discriminator, ElementDefinition, ProfileRegistry, StructureDefinition, ValueConstraint, ValueSetCode,
+};
+use crate::standards::ucum::Ucum;
 
 pub struct FhirValidator;
 
@@ -7,13 +16,667 @@ impl FhirValidator {
     pub fn validate_patient(patient: &Patient) -> Result<(), HimsError> {
         patient.validate()
     }
-    
-    pub fn validate_fhir_json(json: &str) -> Result<(), HimsError> {
-        // Validate FHIR JSON against schema
-        serde_json::from_str::<serde_json::Value>(json)
-            .map_err(|e| HimsError::ValidationError {
-                message: format!("Invalid JSON: {}", e),
-            })?;
-        Ok(())
+
+    pub fn validate_fhir_json(json: &str) -> OperationOutcome {
+        let mut outcome = OperationOutcome::new();
+        match serde_json::from_str::<Value>(json) {
+            Ok(resource) if resource.get("resourceType").and_then(Value::as_str).is_some() => {}
+            Ok(_) => outcome.push(IssueSeverity::Fatal, IssueType::Structure, "", "Missing resourceType"),
+            Err(e) => outcome.push(IssueSeverity::Fatal, IssueType::Structure, "", format!("Invalid JSON: {}", e)),
+        }
+        outcome.finish()
+    }
+}
+
+/// Validates resources against the StructureDefinitions in a registry:
+/// cardinality, fixed and pattern values, slicing and terminology bindings
+pub struct ProfileValidator<'a> {
+    registry: &'a ProfileRegistry,
+}
+
+impl<'a> ProfileValidator<'a> {
+    pub fn new(registry: &'a ProfileRegistry) -> Self {
+        Self { registry }
+    }
+
+    /// Validate against every profile the resource claims in `meta.profile`
+    pub fn validate_declared(&self, resource: &Value) -> OperationOutcome {
+        self.check_declared(resource).finish()
+    }
+
+    /// Validate against the profile `pack` requires for the resource's type,
+    /// and any other profile it claims. The pack must be loaded into the
+    /// registry.
+    pub fn validate_for_pack(&self, resource: &Value, pack: ProfilePack) -> OperationOutcome {
+        let mut outcome = self.check_declared(resource);
+
+        let resource_type = resource.get("resourceType").and_then(Value::as_str).unwrap_or_default();
+        if let Some(required) = pack.profile_for(resource_type) {
+            let declared = resource
+                .pointer("/meta/profile")
+                .and_then(Value::as_array)
+                .is_some_and(|profiles| profiles.iter().any(|profile| profile.as_str() == Some(required)));
+            if !declared {
+                outcome.extend(self.check(resource, required));
+            }
+        }
+        outcome.finish()
+    }
+
+    /// Validate against one profile, by canonical URL
+    pub fn validate(&self, resource: &Value, profile_url: &str) -> OperationOutcome {
+        self.check(resource, profile_url).finish()
+    }
+
+    fn check_declared(&self, resource: &Value) -> OperationOutcome {
+        let profiles: Vec<&str> = resource
+            .pointer("/meta/profile")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+
+        let mut outcome = OperationOutcome::new();
+        for profile in profiles {
+            outcome.extend(self.check(resource, profile));
+        }
+        outcome
+    }
+
+    fn check(&self, resource: &Value, profile_url: &str) -> OperationOutcome {
+        let mut outcome = OperationOutcome::new();
+        let Some(profile) = self.registry.profile(profile_url) else {
+            outcome.push(
+                IssueSeverity::Error,
+                IssueType::NotFound,
+                "",
+                format!("Profile {} is not loaded", profile_url),
+            );
+            return outcome;
+        };
+
+        let resource_type = resource.get("resourceType").and_then(Value::as_str);
+        let Some(object) = resource.as_object().filter(|_| resource_type == Some(profile.resource_type.as_str()))
+        else {
+            outcome.push(
+                IssueSeverity::Fatal,
+                IssueType::Invalid,
+                "",
+                format!(
+                    "{} constrains {}, not {}",
+                    profile_url,
+                    profile.resource_type,
+                    resource_type.unwrap_or("a non-resource")
+                ),
+            );
+            return outcome;
+        };
+
+        let mut walk = Walk {
+            profile,
+            registry: self.registry,
+            outcome,
+        };
+        walk.object(&profile.resource_type, object, &profile.resource_type);
+        walk.outcome
+    }
+}
+
+/// One pass over a resource, collecting issues
+struct Walk<'a> {
+    profile: &'a StructureDefinition,
+    registry: &'a ProfileRegistry,
+    outcome: OperationOutcome,
+}
+
+impl<'a> Walk<'a> {
+    /// Check the children of element `parent_id` present in `object`,
+    /// reporting at the FHIRPath `location`
+    fn object(&mut self, parent_id: &str, object: &Map<String, Value>, location: &str) {
+        for element in self.profile.children(parent_id) {
+            let (name, values) = element_values(element, object);
+            let location = format!("{}.{}", location, name);
+            self.cardinality(element, values.len(), &location);
+
+            if element.slicing.is_some() {
+                self.sliced(element, &values, &location);
+            } else {
+                self.each(element, &values, &location);
+            }
+        }
+    }
+
+    fn each(&mut self, element: &ElementDefinition, values: &[&Value], location: &str) {
+        for (index, value) in values.iter().enumerate() {
+            let location = indexed(location, index, values.len());
+            self.value(element, value, &location);
+        }
+    }
+
+    fn value(&mut self, element: &ElementDefinition, value: &Value, location: &str) {
+        match element.constraint() {
+            Some(ValueConstraint::Fixed(fixed)) if value != fixed => self.outcome.push(
+                IssueSeverity::Error,
+                IssueType::Value,
+                location,
+                format!("Value must be exactly {}", fixed),
+            ),
+            Some(ValueConstraint::Pattern(pattern)) if !matches_pattern(pattern, value) => self.outcome.push(
+                IssueSeverity::Error,
+                IssueType::Value,
+                location,
+                format!("Value must match pattern {}", pattern),
+            ),
+            _ => {}
+        }
+        self.binding(element, value, location);
+        self.ucum(value, location);
+
+        if let Some(object) = value.as_object() {
+            self.object(&element.id, object, location);
+        }
+    }
+
+    /// Quantities coded in UCUM must carry a valid UCUM code
+    fn ucum(&mut self, value: &Value, location: &str) {
+        if value.get("system").and_then(Value::as_str) != Some(UCUM_SYSTEM) {
+            return;
+        }
+        if let Some(Err(e)) = value.get("code").and_then(Value::as_str).map(Ucum::parse) {
+            self.outcome.push(IssueSeverity::Error, IssueType::CodeInvalid, format!("{}.code", location), e.to_string());
+        }
+    }
+
+    fn cardinality(&mut self, element: &ElementDefinition, count: usize, location: &str) {
+        let min = element.min.unwrap_or(0) as usize;
+        if count < min {
+            self.outcome.push(
+                IssueSeverity::Error,
+                IssueType::Required,
+                location,
+                format!("Minimum required = {}, but only found {}", min, count),
+            );
+        }
+        if let Some(max) = element.max_occurs().filter(|max| count > *max) {
+            self.outcome.push(
+                IssueSeverity::Error,
+                IssueType::Structure,
+                location,
+                format!("Maximum allowed = {}, but found {}", max, count),
+            );
+        }
+    }
+
+    /// Assign each value to the first slice whose discriminators it meets,
+    /// then check each slice's cardinality and constraints
+    fn sliced(&mut self, element: &ElementDefinition, values: &[&Value], location: &str) {
+        let Some(slicing) = &element.slicing else {
+            return;
+        };
+        let slices: Vec<&ElementDefinition> = self.profile.slices(&element.id).collect();
+        let mut matched: Vec<Vec<usize>> = vec![Vec::new(); slices.len()];
+
+        for (index, value) in values.iter().enumerate() {
+            let slice = slices
+                .iter()
+                .position(|slice| slicing.discriminator.iter().all(|d| self.discriminates(slice, d, value)));
+            let location = indexed(location, index, values.len());
+
+            match slice {
+                Some(slice) => {
+                    matched[slice].push(index);
+                    self.value(slices[slice], value, &location);
+                }
+                None => {
+                    if slicing.rules == "closed" {
+                        self.outcome.push(
+                            IssueSeverity::Error,
+                            IssueType::Structure,
+                            &location,
+                            "Value matches none of the slices, and slicing is closed",
+                        );
+                    }
+                    self.value(element, value, &location);
+                }
+            }
+        }
+
+        for (slice, indexes) in slices.iter().zip(&matched) {
+            let name = slice.slice_name.as_deref().unwrap_or_default();
+            self.cardinality(slice, indexes.len(), &format!("{}:{}", location, name));
+        }
+    }
+
+    /// Whether `value` meets one discriminator of `slice`
+    fn discriminates(&mut self, slice: &ElementDefinition, discriminator: &Discriminator, value: &Value) -> bool {
+        let element = match discriminator.path.as_str() {
+            "$this" => Some(slice),
+            path => self.profile.element(&format!("{}.{}", slice.id, path)),
+        };
+        let actual = resolve_path(value, &discriminator.path);
+
+        match discriminator.discriminator_type.as_str() {
+            "value" | "pattern" => match element.and_then(ElementDefinition::constraint) {
+                Some(ValueConstraint::Fixed(fixed)) => actual.contains(&fixed),
+                Some(ValueConstraint::Pattern(pattern)) => actual.iter().any(|value| matches_pattern(pattern, value)),
+                // A required binding on the discriminator also separates slices
+                None => match element.and_then(|element| self.required_codes(element)) {
+                    Some(codes) => actual.iter().any(|value| in_value_set(&codes, value)),
+                    None => false,
+                },
+            },
+            "exists" => {
+                let required = element.is_some_and(|element| element.min.unwrap_or(0) > 0);
+                required != actual.is_empty()
+            }
+            "type" => {
+                let types: Vec<&str> = element
+                    .map(|element| element.types.iter().map(|t| t.code.as_str()).collect())
+                    .unwrap_or_default();
+                // Choice slices are named for their type, e.g. value[x]:valueQuantity
+                types.is_empty() || actual.iter().any(|value| types.iter().any(|t| json_matches_type(t, value)))
+            }
+            other => {
+                self.outcome.push(
+                    IssueSeverity::Information,
+                    IssueType::NotSupported,
+                    &slice.id,
+                    format!("Slice discriminator type {} is not checked", other),
+                );
+                false
+            }
+        }
+    }
+
+    fn binding(&mut self, element: &ElementDefinition, value: &Value, location: &str) {
+        let Some(binding) = &element.binding else {
+            return;
+        };
+        let severity = match binding.strength.as_str() {
+            "required" => IssueSeverity::Error,
+            "extensible" => IssueSeverity::Warning,
+            _ => return,
+        };
+        let Some(value_set) = binding.value_set.as_deref() else {
+            return;
+        };
+        // Only coded values are bound; a Coding or CodeableConcept is checked
+        // at its own level, not again at each of its children
+        if !is_coded(value) {
+            return;
+        }
+
+        match self.registry.value_set_codes(value_set) {
+            Some(codes) if !in_value_set(&codes, value) => self.outcome.push(
+                severity,
+                IssueType::CodeInvalid,
+                location,
+                format!("None of the codes provided are in the value set {}", value_set),
+            ),
+            Some(_) => {}
+            None => self.outcome.push(
+                IssueSeverity::Information,
+                IssueType::NotSupported,
+                location,
+                format!("Value set {} is not available, so the binding was not checked", value_set),
+            ),
+        }
+    }
+
+    fn required_codes(&self, element: &ElementDefinition) -> Option<HashSet<ValueSetCode>> {
+        let binding = element.binding.as_ref().filter(|binding| binding.strength == "required")?;
+        self.registry.value_set_codes(binding.value_set.as_deref()?)
+    }
+}
+
+/// Name the element has in `object`, and its values there, arrays flattened.
+/// Choice elements take whichever typed name is present.
+fn element_values<'v>(element: &ElementDefinition, object: &'v Map<String, Value>) -> (String, Vec<&'v Value>) {
+    let name = element.name();
+    let found = match name.strip_suffix("[x]") {
+        Some(base) => object
+            .iter()
+            .find(|(key, _)| key.strip_prefix(base).is_some_and(|rest| rest.starts_with(char::is_uppercase))),
+        None => object.get_key_value(name),
+    };
+
+    match found {
+        Some((key, Value::Array(items))) => (key.clone(), items.iter().collect()),
+        Some((key, Value::Null)) => (key.clone(), Vec::new()),
+        Some((key, value)) => (key.clone(), vec![value]),
+        None => (name.to_string(), Vec::new()),
+    }
+}
+
+/// `location[index]` when the element repeats
+fn indexed(location: &str, index: usize, count: usize) -> String {
+    if count > 1 {
+        format!("{}[{}]", location, index)
+    } else {
+        location.to_string()
+    }
+}
+
+/// Values at a dotted path below `value`, arrays flattened. `$this` is the
+/// value itself.
+fn resolve_path<'v>(value: &'v Value, path: &str) -> Vec<&'v Value> {
+    let mut current = vec![value];
+    for segment in path.split('.').filter(|segment| *segment != "$this") {
+        current = current
+            .into_iter()
+            .filter_map(|value| value.get(segment))
+            .flat_map(|value| match value {
+                Value::Array(items) => items.iter().collect(),
+                value => vec![value],
+            })
+            .collect();
+    }
+    current
+}
+
+/// FHIR pattern semantics: every property in the pattern appears in the
+/// value with a matching value; arrays in the pattern need each item
+/// matched by some item in the value
+fn matches_pattern(pattern: &Value, value: &Value) -> bool {
+    match (pattern, value) {
+        (Value::Object(pattern), Value::Object(value)) => pattern
+            .iter()
+            .all(|(key, expected)| value.get(key).is_some_and(|actual| matches_pattern(expected, actual))),
+        (Value::Array(pattern), Value::Array(value)) => pattern
+            .iter()
+            .all(|expected| value.iter().any(|actual| matches_pattern(expected, actual))),
+        (Value::Array(pattern), value) => pattern.iter().all(|expected| matches_pattern(expected, value)),
+        (pattern, value) => pattern == value,
+    }
+}
+
+fn is_coded(value: &Value) -> bool {
+    match value {
+        Value::String(_) => true,
+        Value::Object(object) => object.contains_key("coding") || object.contains_key("code"),
+        _ => false,
+    }
+}
+
+/// Whether a code, Coding, Quantity or CodeableConcept has a code in the set.
+/// A bare code matches regardless of system.
+fn in_value_set(codes: &HashSet<ValueSetCode>, value: &Value) -> bool {
+    let coding_in = |coding: &Value| {
+        let Some(code) = coding.get("code").and_then(Value::as_str) else {
+            return false;
+        };
+        let system = coding.get("system").and_then(Value::as_str);
+        codes
+            .iter()
+            .any(|listed| listed.code == code && (listed.system.is_none() || listed.system.as_deref() == system))
+    };
+
+    match value {
+        Value::String(code) => codes.iter().any(|listed| listed.code == *code),
+        Value::Object(object) => match object.get("coding").and_then(Value::as_array) {
+            Some(codings) => codings.iter().any(coding_in),
+            None => coding_in(value),
+        },
+        _ => false,
+    }
+}
+
+/// Rough JSON shape check for a FHIR type code, enough to tell choice
+/// slices apart
+fn json_matches_type(type_code: &str, value: &Value) -> bool {
+    match value {
+        Value::Bool(_) => type_code == "boolean",
+        Value::Number(_) => matches!(type_code, "integer" | "decimal" | "positiveInt" | "unsignedInt"),
+        Value::String(_) => type_code.starts_with(char::is_lowercase),
+        Value::Object(_) => type_code.starts_with(char::is_uppercase),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PROFILE: &str = "http://example.org/fhir/StructureDefinition/test-observation";
+    const STATUS_VALUE_SET: &str = "http://example.org/fhir/ValueSet/test-status";
+    const CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/observation-category";
+
+    fn category(code: &str) -> Value {
+        json!({ "coding": [{ "system": CATEGORY_SYSTEM, "code": code }] })
+    }
+
+    fn element(id: &str, min: u32, max: &str, extra: Value) -> Value {
+        let path = id.split('.').map(|segment| segment.split(':').next().unwrap()).collect::<Vec<_>>().join(".");
+        let mut element = json!({ "id": id, "path": path, "min": min, "max": max });
+        if let Some(slice_name) = id.rsplit_once(':').map(|(_, name)| name).filter(|name| !name.contains('.')) {
+            element["sliceName"] = json!(slice_name);
+        }
+        element.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        element
+    }
+
+    /// An Observation profile with one constraint of each kind
+    fn registry() -> ProfileRegistry {
+        let mut registry = ProfileRegistry::new();
+        let elements = vec![
+            element("Observation", 0, "*", json!({})),
+            element("Observation.identifier", 0, "*", json!({})),
+            element(
+                "Observation.identifier.system",
+                1,
+                "1",
+                json!({ "fixedUri": "urn:oid:2.16.840.1.113883.19.5" }),
+            ),
+            element(
+                "Observation.status",
+                1,
+                "1",
+                json!({ "binding": { "strength": "required", "valueSet": STATUS_VALUE_SET } }),
+            ),
+            element(
+                "Observation.category",
+                1,
+                "*",
+                json!({ "slicing": { "discriminator": [{ "type": "pattern", "path": "$this" }], "rules": "closed" } }),
+            ),
+            element(
+                "Observation.category:vitals",
+                1,
+                "1",
+                json!({ "patternCodeableConcept": category("vital-signs") }),
+            ),
+            element(
+                "Observation.category:exam",
+                0,
+                "1",
+                json!({ "patternCodeableConcept": category("exam") }),
+            ),
+            element(
+                "Observation.code",
+                1,
+                "1",
+                json!({ "patternCodeableConcept": { "coding": [{ "system": "http://loinc.org", "code": "8867-4" }] } }),
+            ),
+            element(
+                "Observation.interpretation",
+                0,
+                "1",
+                json!({ "binding": { "strength": "extensible", "valueSet": STATUS_VALUE_SET } }),
+            ),
+            element("Observation.subject", 1, "1", json!({})),
+            element("Observation.value[x]", 0, "1", json!({})),
+        ];
+        registry
+            .add_resource(json!({
+                "resourceType": "Bundle",
+                "entry": [
+                    { "resource": {
+                        "resourceType": "StructureDefinition",
+                        "url": PROFILE,
+                        "type": "Observation",
+                        "snapshot": { "element": elements }
+                    } },
+                    { "resource": {
+                        "resourceType": "ValueSet",
+                        "url": STATUS_VALUE_SET,
+                        "compose": { "include": [{
+                            "system": "http://hl7.org/fhir/observation-status",
+                            "concept": [{ "code": "final" }, { "code": "amended" }]
+                        }] }
+                    } }
+                ]
+            }))
+            .unwrap();
+        registry
+    }
+
+    fn heart_rate() -> Value {
+        json!({
+            "resourceType": "Observation",
+            "identifier": [{ "system": "urn:oid:2.16.840.1.113883.19.5", "value": "obs-1" }],
+            "status": "final",
+            "category": [category("vital-signs")],
+            "code": { "coding": [{ "system": "http://loinc.org", "code": "8867-4", "display": "Heart rate" }] },
+            "subject": { "reference": "Patient/p1" },
+            "valueQuantity": { "value": 72, "unit": "/min", "system": "http://unitsofmeasure.org", "code": "/min" }
+        })
+    }
+
+    /// Locations of the issues at `severity` or worse
+    fn errors(outcome: &OperationOutcome, severity: IssueSeverity) -> Vec<String> {
+        outcome.issues_at_least(severity).flat_map(|issue| issue.expression.clone()).collect()
+    }
+
+    fn check(resource: &Value) -> OperationOutcome {
+        ProfileValidator::new(&registry()).validate(resource, PROFILE)
+    }
+
+    #[test]
+    fn test_conforming_resource() {
+        let outcome = check(&heart_rate());
+        assert!(outcome.is_valid(), "{:?}", outcome);
+        assert!(errors(&outcome, IssueSeverity::Warning).is_empty());
+    }
+
+    #[test]
+    fn test_cardinality() {
+        let mut missing = heart_rate();
+        missing.as_object_mut().unwrap().remove("subject");
+        assert_eq!(errors(&check(&missing), IssueSeverity::Error), vec!["Observation.subject"]);
+
+        let mut repeated = heart_rate();
+        repeated["code"] = json!([repeated["code"].clone(), repeated["code"].clone()]);
+        let outcome = check(&repeated);
+        assert!(outcome.issue.iter().any(|issue| issue.code == IssueType::Structure
+            && issue.expression == vec!["Observation.code".to_string()]));
+
+        let mut nested = heart_rate();
+        nested["identifier"][0].as_object_mut().unwrap().remove("system");
+        assert_eq!(errors(&check(&nested), IssueSeverity::Error), vec!["Observation.identifier.system"]);
+    }
+
+    #[test]
+    fn test_fixed_and_pattern_values() {
+        let mut fixed = heart_rate();
+        fixed["identifier"][0]["system"] = json!("urn:oid:1.2.3");
+        assert_eq!(errors(&check(&fixed), IssueSeverity::Error), vec!["Observation.identifier.system"]);
+
+        // A pattern is met by a value containing it, extra properties and all
+        let mut pattern = heart_rate();
+        pattern["code"]["text"] = json!("Pulse");
+        assert!(check(&pattern).is_valid());
+
+        pattern["code"]["coding"][0]["code"] = json!("8310-5");
+        let outcome = check(&pattern);
+        assert_eq!(errors(&outcome, IssueSeverity::Error), vec!["Observation.code"]);
+        assert_eq!(outcome.issue[0].code, IssueType::Value);
+    }
+
+    #[test]
+    fn test_slicing() {
+        let mut two_slices = heart_rate();
+        two_slices["category"].as_array_mut().unwrap().push(category("exam"));
+        assert!(check(&two_slices).is_valid());
+
+        // The required slice is missing
+        let mut exam_only = heart_rate();
+        exam_only["category"] = json!([category("exam")]);
+        assert_eq!(errors(&check(&exam_only), IssueSeverity::Error), vec!["Observation.category:vitals"]);
+
+        // Closed slicing admits no other values
+        let mut unsliced = heart_rate();
+        unsliced["category"].as_array_mut().unwrap().push(json!({ "coding": [{ "code": "laboratory" }] }));
+        assert_eq!(errors(&check(&unsliced), IssueSeverity::Error), vec!["Observation.category[1]"]);
+
+        // Too many values for a slice
+        let mut repeated = heart_rate();
+        let vitals = repeated["category"][0].clone();
+        repeated["category"].as_array_mut().unwrap().push(vitals);
+        assert_eq!(errors(&check(&repeated), IssueSeverity::Error), vec!["Observation.category:vitals"]);
+    }
+
+    #[test]
+    fn test_bindings() {
+        let mut required = heart_rate();
+        required["status"] = json!("registered");
+        let outcome = check(&required);
+        assert_eq!(errors(&outcome, IssueSeverity::Error), vec!["Observation.status"]);
+        assert_eq!(outcome.issue[0].code, IssueType::CodeInvalid);
+
+        // Codes outside an extensible binding are warnings, not errors
+        let mut extensible = heart_rate();
+        extensible["interpretation"] = json!({ "coding": [{ "system": "http://example.org", "code": "H" }] });
+        let outcome = check(&extensible);
+        assert!(outcome.is_valid());
+        assert_eq!(errors(&outcome, IssueSeverity::Warning), vec!["Observation.interpretation"]);
+
+        extensible["interpretation"] =
+            json!({ "coding": [{ "system": "http://hl7.org/fhir/observation-status", "code": "amended" }] });
+        assert!(errors(&check(&extensible), IssueSeverity::Warning).is_empty());
+    }
+
+    #[test]
+    fn test_ucum_quantities() {
+        let mut quantity = heart_rate();
+        quantity["valueQuantity"]["code"] = json!("beats");
+        assert_eq!(errors(&check(&quantity), IssueSeverity::Error), vec!["Observation.valueQuantity.code"]);
+    }
+
+    #[test]
+    fn test_profile_lookup() {
+        let registry = registry();
+        let validator = ProfileValidator::new(&registry);
+
+        let outcome = validator.validate(&heart_rate(), "http://example.org/unknown");
+        assert_eq!(outcome.issue[0].code, IssueType::NotFound);
+        assert!(!outcome.is_valid());
+
+        let outcome = validator.validate(&json!({ "resourceType": "Patient" }), PROFILE);
+        assert_eq!(outcome.issue[0].severity, IssueSeverity::Fatal);
+
+        // Declared profiles are checked; none declared is nothing to check
+        let mut declared = heart_rate();
+        declared["meta"] = json!({ "profile": [PROFILE] });
+        declared["status"] = json!("registered");
+        assert!(!validator.validate_declared(&declared).is_valid());
+        assert!(validator.validate_declared(&json!({ "resourceType": "Observation" })).is_valid());
+    }
+
+    #[test]
+    fn test_validate_fhir_json() {
+        let outcome = FhirValidator::validate_fhir_json(r#"{"resourceType": "Patient"}"#);
+        assert!(outcome.is_valid());
+        assert_eq!(outcome.issue.len(), 1);
+        assert_eq!(outcome.issue[0].severity, IssueSeverity::Information);
+
+        let outcome = FhirValidator::validate_fhir_json(r#"{"id": "p1"}"#);
+        assert_eq!(outcome.issue[0].severity, IssueSeverity::Fatal);
+        assert_eq!(outcome.issue[0].diagnostics.as_deref(), Some("Missing resourceType"));
+
+        let outcome = FhirValidator::validate_fhir_json("{not json");
+        assert!(!outcome.is_valid());
+        assert_eq!(outcome.issue[0].code, IssueType::Structure);
     }
-}
\ No newline at end of file
+}
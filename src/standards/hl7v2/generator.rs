@@ -1,15 +1,84 @@
+use chrono::{DateTime, Utc};
+
 use crate::core::HimsError;
+use crate::models::{ContactPointSystem, Gender, Patient};
 
 pub struct Hl7Generator;
 
+/// ADT trigger events the outbound feed sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdtEvent {
+    /// Admit/visit notification
+    A01,
+    /// Discharge/end visit
+    A03,
+    /// Register a patient
+    A04,
+    /// Update patient information
+    A08,
+}
+
+impl AdtEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdtEvent::A01 => "A01",
+            AdtEvent::A03 => "A03",
+            AdtEvent::A04 => "A04",
+            AdtEvent::A08 => "A08",
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "A01" => Some(AdtEvent::A01),
+            "A03" => Some(AdtEvent::A03),
+            "A04" => Some(AdtEvent::A04),
+            "A08" => Some(AdtEvent::A08),
+            _ => None,
+        }
+    }
+
+    /// Message structure for MSH-9.3; A04 and A08 reuse the A01 structure
+    fn structure(&self) -> &'static str {
+        match self {
+            AdtEvent::A03 => "ADT_A03",
+            _ => "ADT_A01",
+        }
+    }
+}
+
+/// MSH fields that vary by destination and message
+#[derive(Debug, Clone)]
+pub struct MessageHeader {
+    pub sending_application: String,
+    pub sending_facility: String,
+    pub receiving_application: String,
+    pub receiving_facility: String,
+    /// MSH-10, echoed back in the receiver's MSA-2
+    pub control_id: String,
+    /// MSH-13, consecutive per destination so gaps and repeats show
+    pub sequence_number: Option<i64>,
+}
+
+/// PV1 content for visit events
+#[derive(Debug, Clone, Default)]
+pub struct AdtVisit {
+    /// PV1-2: `O` outpatient, `I` inpatient, `E` emergency
+    pub patient_class: String,
+    pub visit_number: Option<String>,
+    pub location: Option<String>,
+    pub admit_time: Option<DateTime<Utc>>,
+    pub discharge_time: Option<DateTime<Utc>>,
+}
+
 impl Hl7Generator {
     pub fn new() -> Self {
         Self
     }
-    
+
     pub fn generate_ack_message(original_control_id: &str, ack_code: &str) -> Result<String, HimsError> {
         let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
-        
+
         let ack = format!(
             "MSH|^~\\&|HIMS|HOSPITAL|||{}||ACK|{}|P|2.5\r\nMSA|{}|{}\r\n",
             timestamp,
@@ -17,13 +86,162 @@ impl Hl7Generator {
             ack_code,
             original_control_id
         );
-        
+
         Ok(ack)
     }
+
+    /// ADT message (HL7 v2.5) with MSH, EVN, PID and, for visit events, PV1
+    /// segments. Segments end in `\r` as MLLP receivers expect.
+    pub fn generate_adt_message(
+        event: AdtEvent,
+        header: &MessageHeader,
+        patient: &Patient,
+        visit: Option<&AdtVisit>,
+        occurred_at: DateTime<Utc>,
+    ) -> String {
+        let now = ts(Utc::now());
+        let mut segments = vec![
+            format!(
+                "MSH|^~\\&|{}|{}|{}|{}|{}||ADT^{}^{}|{}|P|2.5|{}",
+                escape(&header.sending_application),
+                escape(&header.sending_facility),
+                escape(&header.receiving_application),
+                escape(&header.receiving_facility),
+                now,
+                event.as_str(),
+                event.structure(),
+                escape(&header.control_id),
+                header.sequence_number.map(|n| n.to_string()).unwrap_or_default(),
+            ),
+            format!("EVN|{}|{}", event.as_str(), ts(occurred_at)),
+            Self::pid_segment(patient),
+        ];
+
+        if matches!(event, AdtEvent::A01 | AdtEvent::A03) || visit.is_some() {
+            let visit = visit.cloned().unwrap_or_else(|| AdtVisit {
+                patient_class: "O".to_string(),
+                ..AdtVisit::default()
+            });
+            segments.push(Self::pv1_segment(&visit));
+        }
+
+        let mut message = segments.join("\r");
+        message.push('\r');
+        message
+    }
+
+    fn pid_segment(patient: &Patient) -> String {
+        // PID-3: the server's own ID first, then the patient's business identifiers
+        let mut identifiers = vec![format!("{}^^^HIMS^PI", patient.id)];
+        identifiers.extend(patient.identifier.iter().map(|identifier| {
+            format!(
+                "{}^^^{}",
+                escape(&identifier.value),
+                escape(identifier.system.as_deref().unwrap_or_default()),
+            )
+        }));
+
+        let names: Vec<String> = patient
+            .name
+            .iter()
+            .map(|name| {
+                format!(
+                    "{}^{}^{}^{}^{}",
+                    escape(name.family.as_deref().unwrap_or_default()),
+                    escape(name.given.first().map(String::as_str).unwrap_or_default()),
+                    escape(&name.given.iter().skip(1).cloned().collect::<Vec<_>>().join(" ")),
+                    escape(&name.suffix.join(" ")),
+                    escape(&name.prefix.join(" ")),
+                )
+            })
+            .collect();
+
+        let gender = match patient.gender {
+            Gender::Male => "M",
+            Gender::Female => "F",
+            Gender::Other => "O",
+            Gender::Unknown => "U",
+        };
+
+        let addresses: Vec<String> = patient
+            .address
+            .iter()
+            .map(|address| {
+                format!(
+                    "{}^{}^{}^{}^{}^{}",
+                    escape(address.line.first().map(String::as_str).unwrap_or_default()),
+                    escape(&address.line.iter().skip(1).cloned().collect::<Vec<_>>().join(", ")),
+                    escape(address.city.as_deref().unwrap_or_default()),
+                    escape(address.state.as_deref().unwrap_or_default()),
+                    escape(address.postal_code.as_deref().unwrap_or_default()),
+                    escape(address.country.as_deref().unwrap_or_default()),
+                )
+            })
+            .collect();
+
+        let phones: Vec<String> = patient
+            .telecom
+            .iter()
+            .filter_map(|telecom| match telecom.system {
+                ContactPointSystem::Phone | ContactPointSystem::Sms => {
+                    Some(format!("{}^PRN^PH", escape(&telecom.value)))
+                }
+                ContactPointSystem::Email => Some(format!("^NET^Internet^{}", escape(&telecom.value))),
+                _ => None,
+            })
+            .collect();
+
+        format!(
+            "PID|1||{}||{}||{}|{}|||{}||{}",
+            identifiers.join("~"),
+            names.join("~"),
+            patient.birth_date.map(|date| date.format("%Y%m%d").to_string()).unwrap_or_default(),
+            gender,
+            addresses.join("~"),
+            phones.join("~"),
+        )
+    }
+
+    fn pv1_segment(visit: &AdtVisit) -> String {
+        let mut fields = vec![String::new(); 46];
+        fields[0] = "1".to_string();
+        fields[1] = escape(&visit.patient_class);
+        fields[2] = escape(visit.location.as_deref().unwrap_or_default());
+        fields[18] = escape(visit.visit_number.as_deref().unwrap_or_default());
+        fields[43] = visit.admit_time.map(ts).unwrap_or_default();
+        fields[44] = visit.discharge_time.map(ts).unwrap_or_default();
+        // Trailing empty fields are left off
+        while fields.last().is_some_and(String::is_empty) {
+            fields.pop();
+        }
+        format!("PV1|{}", fields.join("|"))
+    }
+}
+
+/// HL7 timestamp, `YYYYMMDDHHMMSS+0000`
+fn ts(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%d%H%M%S+0000").to_string()
+}
+
+/// Escape the encoding characters in a field value
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\E\\"),
+            '|' => escaped.push_str("\\F\\"),
+            '^' => escaped.push_str("\\S\\"),
+            '&' => escaped.push_str("\\T\\"),
+            '~' => escaped.push_str("\\R\\"),
+            '\r' | '\n' => escaped.push_str("\\X0D\\"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 impl Default for Hl7Generator {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
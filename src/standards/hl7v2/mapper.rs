@@ -7,8 +7,8 @@ use crate::standards::hl7v2::parser::AdtMessage;
 pub struct Hl7Mapper;
 
 impl Hl7Mapper {
-    pub fn adt_to_fhir_patient(adt: &AdtMessage) -> Result<Patient, HimsError> {
-        let mut patient = Patient::new();
+    pub fn adt_to_fhir_patient(_adt: &AdtMessage) -> Result<Patient, HimsError> {
+        let patient = Patient::new();
         
         // Map HL7 ADT to FHIR Patient
         // This is a simplified mapping - real implementation would be more comprehensive
@@ -16,12 +16,3 @@ impl Hl7Mapper {
         Ok(patient)
     }
 }
-
-pub struct Hl7Generator;
-
-impl Hl7Generator {
-    pub fn generate_adt_message(patient: &Patient) -> Result<String, HimsError> {
-        // Generate HL7 ADT message from FHIR Patient
-        Ok("MSH|^~\\&|HIMS|HOSPITAL|||20241016120000||ADT^A01|12345|P|2.5\r\n".to_string())
-    }
-}
\ No newline at end of file
@@ -0,0 +1,121 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::core::HimsError;
+
+/// Start of an MLLP frame
+const START_BLOCK: u8 = 0x0b;
+/// End of an MLLP frame, followed by a carriage return
+const END_BLOCK: u8 = 0x1c;
+const CARRIAGE_RETURN: u8 = 0x0d;
+
+/// Largest acknowledgement read before the connection is given up on
+const MAX_ACK_BYTES: usize = 64 * 1024;
+
+/// Receiver's answer to a message, from its MSA segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Acknowledgement {
+    /// MSA-1: `AA`/`CA` accepted, `AE`/`CE` error, `AR`/`CR` rejected
+    pub code: String,
+    /// MSA-2: control ID of the message acknowledged
+    pub control_id: String,
+    /// MSA-3, or ERR details when present
+    pub text: Option<String>,
+}
+
+impl Acknowledgement {
+    pub fn is_accepted(&self) -> bool {
+        matches!(self.code.as_str(), "AA" | "CA")
+    }
+
+    /// Read the MSA segment of an ACK
+    pub fn parse(ack: &str) -> Result<Self, HimsError> {
+        let msa = ack
+            .split(['\r', '\n'])
+            .find(|segment| segment.starts_with("MSA|"))
+            .ok_or_else(|| HimsError::ValidationError {
+                message: "Acknowledgement has no MSA segment".to_string(),
+            })?;
+        let fields: Vec<&str> = msa.split('|').collect();
+        Ok(Self {
+            code: fields.get(1).copied().unwrap_or_default().to_string(),
+            control_id: fields.get(2).copied().unwrap_or_default().to_string(),
+            text: fields.get(3).filter(|text| !text.is_empty()).map(|text| text.to_string()),
+        })
+    }
+}
+
+/// Wrap a message in an MLLP frame
+pub fn frame(message: &str) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(message.len() + 3);
+    framed.push(START_BLOCK);
+    framed.extend_from_slice(message.as_bytes());
+    framed.push(END_BLOCK);
+    framed.push(CARRIAGE_RETURN);
+    framed
+}
+
+/// Content of one complete MLLP frame at the start of `buffer`, if it has
+/// arrived
+pub fn unframe(buffer: &[u8]) -> Option<&[u8]> {
+    let start = buffer.iter().position(|&b| b == START_BLOCK)?;
+    let end = buffer[start..].windows(2).position(|w| w == [END_BLOCK, CARRIAGE_RETURN])?;
+    Some(&buffer[start + 1..start + end])
+}
+
+/// Minimal Lower Layer Protocol client: one connection per message, sent
+/// and acknowledged before the next
+#[derive(Debug, Clone)]
+pub struct MllpClient {
+    pub timeout: Duration,
+}
+
+impl Default for MllpClient {
+    fn default() -> Self {
+        Self { timeout: Duration::from_secs(30) }
+    }
+}
+
+impl MllpClient {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+
+    /// Send a message to `host:port` and wait for its acknowledgement
+    pub async fn send(&self, host: &str, port: u16, message: &str) -> Result<Acknowledgement, HimsError> {
+        tokio::time::timeout(self.timeout, Self::exchange(host, port, message))
+            .await
+            .map_err(|_| HimsError::NetworkError {
+                message: format!("No acknowledgement from {}:{} within {:?}", host, port, self.timeout),
+            })?
+    }
+
+    async fn exchange(host: &str, port: u16, message: &str) -> Result<Acknowledgement, HimsError> {
+        let network = |e: std::io::Error| HimsError::NetworkError {
+            message: format!("MLLP {}:{}: {}", host, port, e),
+        };
+        let mut stream = TcpStream::connect((host, port)).await.map_err(network)?;
+        stream.write_all(&frame(message)).await.map_err(network)?;
+
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut chunk).await.map_err(network)?;
+            if read == 0 {
+                return Err(HimsError::NetworkError {
+                    message: format!("{}:{} closed the connection without acknowledging", host, port),
+                });
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+            if let Some(ack) = unframe(&buffer) {
+                return Acknowledgement::parse(&String::from_utf8_lossy(ack));
+            }
+            if buffer.len() > MAX_ACK_BYTES {
+                return Err(HimsError::NetworkError {
+                    message: format!("{}:{} sent an oversized acknowledgement", host, port),
+                });
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
 pub mod parser;
 pub mod mapper;
 pub mod generator;
+#[cfg(feature = "server")]
+pub mod mllp;
 
 pub use parser::*;
 pub use mapper::*;
-pub use generator::*;
\ No newline at end of file
+pub use generator::*;
+#[cfg(feature = "server")]
+pub use mllp::*;
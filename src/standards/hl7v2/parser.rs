@@ -1,5 +1,4 @@
 use crate::core::HimsError;
-use std::collections::HashMap;
 
 /// HL7v2 Message structure
 #[derive(Debug, Clone)]
@@ -17,6 +16,12 @@ pub struct Hl7Segment {
 /// HL7v2 Parser for common message types
 pub struct Hl7Parser;
 
+impl Default for Hl7Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Hl7Parser {
     pub fn new() -> Self {
         Self
@@ -24,7 +29,8 @@ impl Hl7Parser {
 
     /// Parse an HL7v2 message from string
     pub fn parse_message(&self, message: &str) -> Result<Hl7Message, HimsError> {
-        let lines: Vec<&str> = message.trim().split('\n').collect();
+        // Segments end with \r per the standard, but files often use \n
+        let lines: Vec<&str> = message.trim().split(['\r', '\n']).filter(|line| !line.is_empty()).collect();
         
         if lines.is_empty() {
             return Err(HimsError::ValidationError {
@@ -52,6 +58,31 @@ impl Hl7Parser {
         })
     }
 
+    /// Split an HL7 batch file into its messages. File and batch header
+    /// and trailer segments (FHS, BHS, BTS, FTS) are dropped, and every
+    /// message comes back with `\r` segment terminators.
+    pub fn split_batch(&self, batch: &str) -> Vec<String> {
+        let mut messages: Vec<Vec<&str>> = Vec::new();
+
+        for segment in batch.split(['\r', '\n']).map(str::trim_end).filter(|segment| !segment.is_empty()) {
+            match segment.get(0..3) {
+                Some("FHS") | Some("BHS") | Some("BTS") | Some("FTS") => {}
+                Some("MSH") => messages.push(vec![segment]),
+                // Segments before the first MSH belong to no message
+                _ => {
+                    if let Some(message) = messages.last_mut() {
+                        message.push(segment);
+                    }
+                }
+            }
+        }
+
+        messages
+            .into_iter()
+            .map(|segments| format!("{}\r", segments.join("\r")))
+            .collect()
+    }
+
     /// Parse a single HL7 segment
     fn parse_segment(&self, segment_line: &str) -> Result<Hl7Segment, HimsError> {
         if segment_line.len() < 3 {
@@ -84,11 +115,10 @@ impl Hl7Parser {
         
         for segment in &hl7_msg.segments {
             match segment.segment_type.as_str() {
-                "MSH" => {
-                    if segment.fields.len() > 8 {
+                "MSH"
+                    if segment.fields.len() > 8 => {
                         adt.message_type = segment.fields[8].clone();
                     }
-                }
                 "PID" => {
                     adt.patient_info = self.parse_pid_segment(segment)?;
                 }
@@ -4,10 +4,12 @@ pub mod dicom;
 pub mod terminology;
 pub mod abdm;
 pub mod accreditation;
+pub mod ucum;
 
 pub use fhir::*;
 pub use hl7v2::*;
 pub use dicom::*;
 pub use terminology::*;
 pub use abdm::*;
-pub use accreditation::*;
\ No newline at end of file
+pub use accreditation::*;
+pub use ucum::*;
\ No newline at end of file
@@ -1,5 +1,36 @@
+use crate::models::constants::{
+    CPT_SYSTEM, HCPCS_SYSTEM, ICD_10_CM_SYSTEM, ICD_10_SYSTEM, LOINC_SYSTEM, NDC_SYSTEM, RXNORM_SYSTEM,
+    SNOMED_CT_SYSTEM, UCUM_SYSTEM,
+};
+
 pub struct TerminologyService;
 
+/// Verhoeff dihedral group multiplication table
+const VERHOEFF_D: [[u8; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+
+/// Verhoeff position permutation table
+const VERHOEFF_P: [[u8; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+];
+
 impl TerminologyService {
     pub fn lookup_loinc_code(_code: &str) -> Result<String, crate::core::HimsError> {
         Ok("LOINC code description".to_string()) // Placeholder
@@ -8,4 +39,136 @@ impl TerminologyService {
     pub fn lookup_snomed_code(_code: &str) -> Result<String, crate::core::HimsError> {
         Ok("SNOMED CT code description".to_string()) // Placeholder
     }
-}
\ No newline at end of file
+
+    /// Whether `code` is a well-formed SNOMED CT concept identifier: 6 to 18
+    /// digits without a leading zero, a concept partition (`00` for the
+    /// international release, `10` for extensions) and a valid Verhoeff
+    /// check digit. Says nothing of whether the concept exists.
+    pub fn is_snomed_concept_id(code: &str) -> bool {
+        let digits = code.as_bytes();
+        if !(6..=18).contains(&digits.len()) || digits[0] == b'0' || !digits.iter().all(u8::is_ascii_digit) {
+            return false;
+        }
+        let partition = &code[code.len() - 3..code.len() - 1];
+        if partition != "00" && partition != "10" {
+            return false;
+        }
+        let check = digits
+            .iter()
+            .rev()
+            .enumerate()
+            .fold(0u8, |check, (i, digit)| {
+                VERHOEFF_D[check as usize][VERHOEFF_P[i % 8][(digit - b'0') as usize] as usize]
+            });
+        check == 0
+    }
+
+    /// Whether `code` is shaped like a CPT code: four digits then a digit
+    /// (Category I), `F` (Category II), `T` (Category III) or `U`
+    /// (proprietary laboratory analyses)
+    pub fn is_cpt_code(code: &str) -> bool {
+        let bytes = code.as_bytes();
+        bytes.len() == 5
+            && bytes[..4].iter().all(u8::is_ascii_digit)
+            && (bytes[4].is_ascii_digit() || matches!(bytes[4], b'F' | b'T' | b'U'))
+    }
+
+    /// Whether `code` is shaped like a HCPCS Level II code: a letter from A
+    /// to V then four digits
+    pub fn is_hcpcs_code(code: &str) -> bool {
+        let bytes = code.as_bytes();
+        bytes.len() == 5 && (b'A'..=b'V').contains(&bytes[0]) && bytes[1..].iter().all(u8::is_ascii_digit)
+    }
+
+    /// Whether `modifier` is shaped like a CPT or HCPCS modifier: two
+    /// digits or upper-case letters, e.g. `25`, `LT`, `F1`
+    pub fn is_modifier(modifier: &str) -> bool {
+        modifier.len() == 2 && modifier.bytes().all(|b| b.is_ascii_digit() || b.is_ascii_uppercase())
+    }
+
+    /// An NDC in the 11-digit 5-4-2 form billing uses. A 10-digit NDC
+    /// needs its hyphens (4-4-2, 5-3-2 or 5-4-1) to tell which segment to
+    /// pad with a zero; 11 digits are taken as they are.
+    pub fn normalize_ndc(ndc: &str) -> Option<String> {
+        let ndc = ndc.trim();
+        let segments: Vec<&str> = ndc.split('-').collect();
+        if !segments.iter().all(|segment| !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit())) {
+            return None;
+        }
+        let lengths: Vec<usize> = segments.iter().map(|segment| segment.len()).collect();
+        match lengths.as_slice() {
+            [11] => Some(ndc.to_string()),
+            [5, 4, 2] => Some(segments.concat()),
+            [4, 4, 2] => Some(format!("0{}{}{}", segments[0], segments[1], segments[2])),
+            [5, 3, 2] => Some(format!("{}0{}{}", segments[0], segments[1], segments[2])),
+            [5, 4, 1] => Some(format!("{}{}0{}", segments[0], segments[1], segments[2])),
+            _ => None,
+        }
+    }
+
+    /// The OMOP vocabulary a FHIR code system's codes are in, as OMOP's
+    /// VOCABULARY table names it
+    pub fn omop_vocabulary_id(system: &str) -> Option<&'static str> {
+        match system {
+            SNOMED_CT_SYSTEM => Some("SNOMED"),
+            LOINC_SYSTEM => Some("LOINC"),
+            RXNORM_SYSTEM => Some("RxNorm"),
+            NDC_SYSTEM => Some("NDC"),
+            ICD_10_CM_SYSTEM => Some("ICD10CM"),
+            ICD_10_SYSTEM => Some("ICD10"),
+            CPT_SYSTEM => Some("CPT4"),
+            HCPCS_SYSTEM => Some("HCPCS"),
+            UCUM_SYSTEM => Some("UCUM"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snomed_concept_ids() {
+        // Diabetes mellitus, essential hypertension, and an extension concept
+        for code in ["73211009", "59621000", "999000011000000103"] {
+            assert!(TerminologyService::is_snomed_concept_id(code), "{}", code);
+        }
+        // Bad check digit, description partition, leading zero, not digits
+        for code in ["73211008", "1234567014", "073211009", "7321100X", "12345"] {
+            assert!(!TerminologyService::is_snomed_concept_id(code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_billing_codes() {
+        for code in ["99213", "3074F", "0042T", "0001U"] {
+            assert!(TerminologyService::is_cpt_code(code), "{}", code);
+        }
+        for code in ["9921", "99213A", "J1100", "0042t"] {
+            assert!(!TerminologyService::is_cpt_code(code), "{}", code);
+        }
+        assert!(TerminologyService::is_hcpcs_code("J1100"));
+        assert!(!TerminologyService::is_hcpcs_code("W1100"));
+        assert!(!TerminologyService::is_hcpcs_code("99213"));
+        assert!(TerminologyService::is_modifier("25") && TerminologyService::is_modifier("LT"));
+        assert!(!TerminologyService::is_modifier("lt") && !TerminologyService::is_modifier("251"));
+
+        assert_eq!(TerminologyService::normalize_ndc("0002-1433-80").as_deref(), Some("00002143380"));
+        assert_eq!(TerminologyService::normalize_ndc("12345-678-90").as_deref(), Some("12345067890"));
+        assert_eq!(TerminologyService::normalize_ndc("12345-6789-1").as_deref(), Some("12345678901"));
+        assert_eq!(TerminologyService::normalize_ndc("00002143380").as_deref(), Some("00002143380"));
+        assert_eq!(TerminologyService::normalize_ndc("0002143380"), None);
+        assert_eq!(TerminologyService::normalize_ndc("50090-347-0"), None);
+        assert_eq!(TerminologyService::normalize_ndc("0002-14A3-80"), None);
+    }
+
+    #[test]
+    fn test_omop_vocabularies() {
+        assert_eq!(TerminologyService::omop_vocabulary_id(SNOMED_CT_SYSTEM), Some("SNOMED"));
+        assert_eq!(TerminologyService::omop_vocabulary_id(RXNORM_SYSTEM), Some("RxNorm"));
+        assert_eq!(TerminologyService::omop_vocabulary_id(ICD_10_CM_SYSTEM), Some("ICD10CM"));
+        assert_eq!(TerminologyService::omop_vocabulary_id(CPT_SYSTEM), Some("CPT4"));
+        assert_eq!(TerminologyService::omop_vocabulary_id("http://example.org/local"), None);
+    }
+}
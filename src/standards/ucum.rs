@@ -0,0 +1,468 @@
+//! UCUM (Unified Code for Units of Measure)
+//!
+//! Parses case-sensitive UCUM codes such as `mg/dL`, `10*9/L`, `mm[Hg]` or
+//! `mL/min/{1.73_m2}` into a magnitude over the base units and the
+//! exponent of each base unit, so that codes can be validated, checked for
+//! commensurability and converted before a value is stored.
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use crate::core::HimsError;
+
+/// Exponent of each base unit. Arbitrary units such as `[IU]` are measured
+/// against nothing else, so each is a base of its own.
+type Dimension = BTreeMap<&'static str, i32>;
+
+/// Units on an interval scale, converted with an offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Special {
+    Celsius,
+    Fahrenheit,
+}
+
+struct Atom {
+    code: &'static str,
+    /// Whether the atom takes a metric prefix
+    metric: bool,
+    /// Magnitude in base units
+    factor: f64,
+    dimension: &'static [(&'static str, i32)],
+    special: Option<Special>,
+}
+
+const fn atom(code: &'static str, metric: bool, factor: f64, dimension: &'static [(&'static str, i32)]) -> Atom {
+    Atom { code, metric, factor, dimension, special: None }
+}
+
+const LENGTH: &[(&str, i32)] = &[("m", 1)];
+const VOLUME: &[(&str, i32)] = &[("m", 3)];
+const TIME: &[(&str, i32)] = &[("s", 1)];
+const MASS: &[(&str, i32)] = &[("g", 1)];
+const PRESSURE: &[(&str, i32)] = &[("g", 1), ("m", -1), ("s", -2)];
+const ENERGY: &[(&str, i32)] = &[("g", 1), ("m", 2), ("s", -2)];
+const AMOUNT: &[(&str, i32)] = &[("mol", 1)];
+const CATALYTIC_ACTIVITY: &[(&str, i32)] = &[("mol", 1), ("s", -1)];
+const TEMPERATURE: &[(&str, i32)] = &[("K", 1)];
+const DIMENSIONLESS: &[(&str, i32)] = &[];
+
+/// The base units, and the derived and customary units seen in clinical
+/// data
+const ATOMS: &[Atom] = &[
+    atom("m", true, 1.0, LENGTH),
+    atom("s", true, 1.0, TIME),
+    atom("g", true, 1.0, MASS),
+    atom("rad", true, 1.0, &[("rad", 1)]),
+    atom("K", true, 1.0, TEMPERATURE),
+    atom("C", true, 1.0, &[("C", 1)]),
+    atom("cd", true, 1.0, &[("cd", 1)]),
+    atom("mol", true, 1.0, AMOUNT),
+    atom("%", false, 0.01, DIMENSIONLESS),
+    atom("[ppth]", false, 1e-3, DIMENSIONLESS),
+    atom("[ppm]", false, 1e-6, DIMENSIONLESS),
+    atom("[ppb]", false, 1e-9, DIMENSIONLESS),
+    atom("[pi]", false, PI, DIMENSIONLESS),
+    atom("[HPF]", false, 1.0, DIMENSIONLESS),
+    atom("[LPF]", false, 1.0, DIMENSIONLESS),
+    atom("L", true, 1e-3, VOLUME),
+    atom("l", true, 1e-3, VOLUME),
+    atom("min", false, 60.0, TIME),
+    atom("h", false, 3_600.0, TIME),
+    atom("d", false, 86_400.0, TIME),
+    atom("wk", false, 604_800.0, TIME),
+    atom("mo", false, 2_629_800.0, TIME),
+    atom("a", false, 31_557_600.0, TIME),
+    atom("Hz", true, 1.0, &[("s", -1)]),
+    atom("N", true, 1e3, &[("g", 1), ("m", 1), ("s", -2)]),
+    atom("Pa", true, 1e3, PRESSURE),
+    atom("bar", true, 1e8, PRESSURE),
+    atom("atm", false, 101_325e3, PRESSURE),
+    atom("m[Hg]", true, 133_322_387.415, PRESSURE),
+    atom("m[H2O]", true, 9_806_650.0, PRESSURE),
+    atom("J", true, 1e3, ENERGY),
+    atom("cal", true, 4_184.0, ENERGY),
+    atom("W", true, 1e3, &[("g", 1), ("m", 2), ("s", -3)]),
+    atom("deg", false, PI / 180.0, &[("rad", 1)]),
+    atom("eq", true, 1.0, AMOUNT),
+    atom("osm", true, 1.0, AMOUNT),
+    atom("kat", true, 1.0, CATALYTIC_ACTIVITY),
+    atom("U", true, 1e-6 / 60.0, CATALYTIC_ACTIVITY),
+    atom("t", true, 1e6, MASS),
+    atom("[in_i]", false, 0.0254, LENGTH),
+    atom("[ft_i]", false, 0.3048, LENGTH),
+    atom("[yd_i]", false, 0.9144, LENGTH),
+    atom("[mi_i]", false, 1_609.344, LENGTH),
+    atom("[lb_av]", false, 453.592_37, MASS),
+    atom("[oz_av]", false, 28.349_523_125, MASS),
+    atom("[foz_us]", false, 2.957_352_956_25e-5, VOLUME),
+    atom("[gal_us]", false, 3.785_411_784e-3, VOLUME),
+    atom("[IU]", true, 1.0, &[("[IU]", 1)]),
+    atom("[iU]", true, 1.0, &[("[IU]", 1)]),
+    atom("[arb'U]", false, 1.0, &[("[arb'U]", 1)]),
+    Atom { code: "Cel", metric: true, factor: 1.0, dimension: TEMPERATURE, special: Some(Special::Celsius) },
+    Atom { code: "[degF]", metric: false, factor: 5.0 / 9.0, dimension: TEMPERATURE, special: Some(Special::Fahrenheit) },
+];
+
+/// Metric prefixes, two-letter `da` first so it wins over `d`
+const PREFIXES: &[(&str, f64)] = &[
+    ("da", 1e1),
+    ("Y", 1e24),
+    ("Z", 1e21),
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+    ("a", 1e-18),
+    ("z", 1e-21),
+    ("y", 1e-24),
+];
+
+/// A parsed UCUM unit
+#[derive(Debug, Clone, PartialEq)]
+pub struct UcumUnit {
+    code: String,
+    factor: f64,
+    dimension: Dimension,
+    special: Option<Special>,
+}
+
+impl UcumUnit {
+    fn unity() -> Self {
+        Self { code: String::new(), factor: 1.0, dimension: Dimension::new(), special: None }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Whether values in the two units measure the same kind of quantity
+    /// and so convert into each other
+    pub fn is_commensurable(&self, other: &UcumUnit) -> bool {
+        self.dimension == other.dimension
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimension.is_empty()
+    }
+
+    /// `value` in this unit, in base units
+    fn in_base(&self, value: f64) -> f64 {
+        match self.special {
+            Some(Special::Celsius) => value + 273.15,
+            Some(Special::Fahrenheit) => (value + 459.67) * 5.0 / 9.0,
+            None => value * self.factor,
+        }
+    }
+
+    /// `value` in base units, in this unit
+    fn in_unit(&self, value: f64) -> f64 {
+        match self.special {
+            Some(Special::Celsius) => value - 273.15,
+            Some(Special::Fahrenheit) => value * 9.0 / 5.0 - 459.67,
+            None => value / self.factor,
+        }
+    }
+
+    /// This unit times `other` raised to `exponent`
+    fn times(mut self, other: &UcumUnit, exponent: i32) -> Self {
+        self.factor *= other.factor.powi(exponent);
+        for (base, power) in &other.dimension {
+            *self.dimension.entry(*base).or_insert(0) += power * exponent;
+        }
+        self.dimension.retain(|_, power| *power != 0);
+        self
+    }
+}
+
+fn invalid(code: &str, message: impl std::fmt::Display) -> HimsError {
+    HimsError::ValidationError { message: format!("{:?} is not a valid UCUM unit: {}", code, message) }
+}
+
+/// Recursive-descent parser over the UCUM grammar:
+/// `term := '/'? component (('.' | '/') component)*`,
+/// `component := '(' term ')' | annotation | factor | simple-unit exponent? annotation?`
+struct Parser<'a> {
+    code: &'a str,
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(code: &'a str) -> Self {
+        Self { code, chars: code.chars().collect(), position: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> HimsError {
+        invalid(self.code, format!("{} at position {}", message, self.position + 1))
+    }
+
+    fn parse(mut self) -> Result<UcumUnit, HimsError> {
+        if self.chars.is_empty() {
+            return Err(invalid(self.code, "the code is empty"));
+        }
+        let unit = self.term()?;
+        match self.peek() {
+            None => Ok(UcumUnit { code: self.code.to_string(), ..unit }),
+            Some(c) => Err(self.error(format!("unexpected {:?}", c))),
+        }
+    }
+
+    fn term(&mut self) -> Result<UcumUnit, HimsError> {
+        let mut unit = if self.peek() == Some('/') { UcumUnit::unity() } else { self.component()? };
+        while let Some(operator @ ('.' | '/')) = self.peek() {
+            self.position += 1;
+            let component = self.component()?;
+            if unit.special.is_some() || component.special.is_some() {
+                return Err(self.error("Cel and [degF] cannot be combined with other units"));
+            }
+            unit = unit.times(&component, if operator == '/' { -1 } else { 1 });
+        }
+        Ok(unit)
+    }
+
+    fn component(&mut self) -> Result<UcumUnit, HimsError> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let unit = self.term()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                self.position += 1;
+                self.annotation()?;
+                Ok(unit)
+            }
+            Some('{') => {
+                self.annotation()?;
+                Ok(UcumUnit::unity())
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let digits = self.digits();
+                if digits == "10" && matches!(self.peek(), Some('*' | '^')) {
+                    // 10*3 is a thousand
+                    self.position += 1;
+                    let exponent = self.exponent()?.ok_or_else(|| self.error("expected an exponent"))?;
+                    self.annotation()?;
+                    return Ok(UcumUnit { factor: 10f64.powi(exponent), ..UcumUnit::unity() });
+                }
+                let factor: f64 = digits.parse().map_err(|_| self.error("invalid factor"))?;
+                self.annotation()?;
+                Ok(UcumUnit { factor, ..UcumUnit::unity() })
+            }
+            Some(_) => {
+                let start = self.position;
+                let symbol = self.symbol()?;
+                let mut unit = lookup(&symbol).ok_or_else(|| {
+                    invalid(self.code, format!("unknown unit {:?} at position {}", symbol, start + 1))
+                })?;
+                match self.exponent()? {
+                    Some(exponent) if unit.special.is_some() && exponent != 1 => {
+                        return Err(self.error("Cel and [degF] cannot take an exponent"));
+                    }
+                    Some(exponent) if exponent != 1 => unit = UcumUnit::unity().times(&unit, exponent),
+                    _ => {}
+                }
+                self.annotation()?;
+                Ok(unit)
+            }
+            None => Err(self.error("expected a unit")),
+        }
+    }
+
+    fn digits(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    /// Signed integer exponent, if one follows
+    fn exponent(&mut self) -> Result<Option<i32>, HimsError> {
+        let negative = match self.peek() {
+            Some('-') => true,
+            Some('+') => false,
+            Some(c) if c.is_ascii_digit() => false,
+            _ => return Ok(None),
+        };
+        if matches!(self.peek(), Some('-' | '+')) {
+            self.position += 1;
+        }
+        let digits = self.digits();
+        let exponent: i32 = digits.parse().map_err(|_| self.error("expected exponent digits"))?;
+        Ok(Some(if negative { -exponent } else { exponent }))
+    }
+
+    /// Annotations like `{titer}` carry no meaning in conversion
+    fn annotation(&mut self) -> Result<(), HimsError> {
+        if self.peek() != Some('{') {
+            return Ok(());
+        }
+        match self.chars[self.position..].iter().position(|c| *c == '}') {
+            Some(end) => {
+                self.position += end + 1;
+                Ok(())
+            }
+            None => Err(self.error("unclosed annotation")),
+        }
+    }
+
+    /// Characters of a unit symbol, including bracketed parts such as
+    /// `[in_i]` or `m[H2O]`
+    fn symbol(&mut self) -> Result<String, HimsError> {
+        let mut symbol = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '[' => {
+                    let end = self.chars[self.position..]
+                        .iter()
+                        .position(|c| *c == ']')
+                        .ok_or_else(|| self.error("unclosed '['"))?;
+                    symbol.extend(&self.chars[self.position..=self.position + end]);
+                    self.position += end + 1;
+                }
+                '.' | '/' | '(' | ')' | '{' | '}' | '+' | '-' => break,
+                c if c.is_ascii_digit() => break,
+                c if c.is_whitespace() => return Err(self.error("UCUM codes contain no spaces")),
+                c => {
+                    symbol.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+        if symbol.is_empty() {
+            return Err(self.error("expected a unit"));
+        }
+        Ok(symbol)
+    }
+}
+
+/// The unit a symbol names: an atom, or a metric atom with a prefix
+fn lookup(symbol: &str) -> Option<UcumUnit> {
+    let of = |atom: &Atom, prefix: f64| UcumUnit {
+        code: symbol.to_string(),
+        factor: atom.factor * prefix,
+        dimension: atom.dimension.iter().copied().collect(),
+        special: atom.special,
+    };
+
+    if let Some(atom) = ATOMS.iter().find(|atom| atom.code == symbol) {
+        return Some(of(atom, 1.0));
+    }
+    PREFIXES.iter().find_map(|(prefix, scale)| {
+        let rest = symbol.strip_prefix(prefix)?;
+        let atom = ATOMS.iter().find(|atom| atom.metric && atom.code == rest)?;
+        // Prefixed special units would need the prefix applied before the
+        // offset; none are used clinically
+        if atom.special.is_some() {
+            return None;
+        }
+        Some(of(atom, *scale))
+    })
+}
+
+/// UCUM parsing, validation and conversion
+pub struct Ucum;
+
+impl Ucum {
+    pub fn parse(code: &str) -> Result<UcumUnit, HimsError> {
+        Parser::new(code).parse()
+    }
+
+    pub fn is_valid(code: &str) -> bool {
+        Self::parse(code).is_ok()
+    }
+
+    /// `value` in unit `from`, in unit `to`
+    pub fn convert(value: f64, from: &str, to: &str) -> Result<f64, HimsError> {
+        let (from, to) = (Self::parse(from)?, Self::parse(to)?);
+        if !from.is_commensurable(&to) {
+            return Err(HimsError::ValidationError {
+                message: format!("{} cannot be converted to {}", from.code, to.code),
+            });
+        }
+        Ok(to.in_unit(from.in_base(value)))
+    }
+
+    /// `value` in unit `from`, in unit `to`, where one measures mass and the
+    /// other amount of substance, e.g. mg/dL and mmol/L of glucose;
+    /// `molar_mass` is the substance's in g/mol
+    pub fn convert_substance(value: f64, from: &str, to: &str, molar_mass: f64) -> Result<f64, HimsError> {
+        let (from, to) = (Self::parse(from)?, Self::parse(to)?);
+        if from.is_commensurable(&to) {
+            return Ok(to.in_unit(from.in_base(value)));
+        }
+
+        // Moles gained must be grams lost, one for one, and nothing else differ
+        let power = |unit: &UcumUnit, base: &str| unit.dimension.get(base).copied().unwrap_or(0);
+        let moles = power(&to, "mol") - power(&from, "mol");
+        let mut bridged = from.dimension.clone();
+        bridged.insert("mol", power(&from, "mol") + moles);
+        bridged.insert("g", power(&from, "g") - moles);
+        bridged.retain(|_, power| *power != 0);
+        if moles == 0 || bridged != to.dimension || !(molar_mass.is_finite() && molar_mass > 0.0) {
+            return Err(HimsError::ValidationError {
+                message: format!("{} cannot be converted to {}", from.code, to.code),
+            });
+        }
+        Ok(to.in_unit(from.in_base(value) * molar_mass.powi(-moles)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_parse() {
+        for code in [
+            "mg/dL", "mmol/L", "10*9/L", "10^3/uL", "/min", "mm[Hg]", "cm[H2O]", "kg/m2", "mL/min/{1.73_m2}",
+            "[IU]/L", "U/L", "Cel", "[degF]", "%", "{cells}/[HPF]", "g.m-2", "mL/(24.h)", "fL", "daL", "1/d",
+        ] {
+            assert!(Ucum::is_valid(code), "{}", code);
+        }
+        for code in ["", "mEq/L", "mg/dl ", "kg m2", "xyz", "m/", "(mg", "{x", "Cel/s", "mCel", "m^"] {
+            assert!(!Ucum::is_valid(code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        assert!(close(Ucum::convert(1.0, "g/dL", "mg/dL").unwrap(), 1000.0));
+        assert!(close(Ucum::convert(1.0, "kPa", "mm[Hg]").unwrap(), 7.500_615_758));
+        assert!(close(Ucum::convert(98.6, "[degF]", "Cel").unwrap(), 37.0));
+        assert!(close(Ucum::convert(5.0, "10*3/uL", "10*9/L").unwrap(), 5.0));
+        assert!(close(Ucum::convert(2.0, "h", "min").unwrap(), 120.0));
+        assert!(close(Ucum::convert(1.0, "mL/(24.h)", "uL/min").unwrap(), 1000.0 / 1440.0));
+        assert!(Ucum::convert(1.0, "mg/dL", "mmol/L").is_err());
+        assert!(Ucum::convert(1.0, "[IU]/L", "U/L").is_err());
+    }
+
+    #[test]
+    fn test_convert_substance() {
+        // Glucose, 180.156 g/mol: 100 mg/dL is 5.55 mmol/L
+        let mmol = Ucum::convert_substance(100.0, "mg/dL", "mmol/L", 180.156).unwrap();
+        assert!((mmol - 5.5507).abs() < 1e-4);
+        let mg = Ucum::convert_substance(mmol, "mmol/L", "mg/dL", 180.156).unwrap();
+        assert!(close(mg, 100.0));
+        assert!(Ucum::convert_substance(1.0, "mg/dL", "mmol/kg", 180.156).is_err());
+    }
+}
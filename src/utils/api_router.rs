@@ -0,0 +1,192 @@
+// src/utils/api_router.rs
+//! Routers that record what they serve
+//!
+//! `ApiRouter` wraps an axum `Router` and keeps, for every route added, its
+//! method, path and a one-line summary. The CapabilityStatement and the
+//! OpenAPI document are generated from these records, so they list exactly
+//! the routes the server mounts.
+
+use axum::{
+    handler::Handler,
+    http::Method,
+    routing::{delete, get, patch, post, put, MethodRouter},
+    Router,
+};
+
+/// A route as mounted: axum path syntax (`/:id`) and a summary for docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiRoute {
+    pub method: Method,
+    pub path: String,
+    pub summary: &'static str,
+}
+
+impl ApiRoute {
+    /// Path parameters, in order (`/:id/keys/:key_id` gives `id`, `key_id`)
+    pub fn path_params(&self) -> Vec<&str> {
+        self.path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')))
+            .collect()
+    }
+
+    /// Path in OpenAPI syntax (`/{id}`)
+    pub fn openapi_path(&self) -> String {
+        self.path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')) {
+                Some(param) => format!("{{{}}}", param),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// An axum router plus the routes it serves
+pub struct ApiRouter<S = ()> {
+    router: Router<S>,
+    routes: Vec<ApiRoute>,
+}
+
+impl<S> Default for ApiRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> ApiRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            router: Router::new(),
+            routes: Vec::new(),
+        }
+    }
+
+    pub fn get<H, T>(self, path: &str, handler: H, summary: &'static str) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::GET, path, get(handler), summary)
+    }
+
+    pub fn post<H, T>(self, path: &str, handler: H, summary: &'static str) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::POST, path, post(handler), summary)
+    }
+
+    pub fn put<H, T>(self, path: &str, handler: H, summary: &'static str) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::PUT, path, put(handler), summary)
+    }
+
+    pub fn patch<H, T>(self, path: &str, handler: H, summary: &'static str) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::PATCH, path, patch(handler), summary)
+    }
+
+    pub fn delete<H, T>(self, path: &str, handler: H, summary: &'static str) -> Self
+    where
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        self.route(Method::DELETE, path, delete(handler), summary)
+    }
+
+    fn route(mut self, method: Method, path: &str, method_router: MethodRouter<S>, summary: &'static str) -> Self {
+        self.router = self.router.route(path, method_router);
+        self.routes.push(ApiRoute {
+            method,
+            path: path.to_string(),
+            summary,
+        });
+        self
+    }
+
+    /// Provide the state, as `Router::with_state`
+    pub fn with_state<S2>(self, state: S) -> ApiRouter<S2>
+    where
+        S2: Clone + Send + Sync + 'static,
+    {
+        ApiRouter {
+            router: self.router.with_state(state),
+            routes: self.routes,
+        }
+    }
+
+    pub fn merge(mut self, other: ApiRouter<S>) -> Self {
+        self.router = self.router.merge(other.router);
+        self.routes.extend(other.routes);
+        self
+    }
+
+    /// Mount `other` under `prefix`, as `Router::nest`
+    pub fn nest(mut self, prefix: &str, other: ApiRouter<S>) -> Self {
+        self.router = self.router.nest(prefix, other.router);
+        self.routes.extend(other.routes.into_iter().map(|route| ApiRoute {
+            path: join_path(prefix, &route.path),
+            ..route
+        }));
+        self
+    }
+
+    pub fn routes(&self) -> &[ApiRoute] {
+        &self.routes
+    }
+
+    pub fn into_parts(self) -> (Router<S>, Vec<ApiRoute>) {
+        (self.router, self.routes)
+    }
+
+    pub fn into_router(self) -> Router<S> {
+        self.router
+    }
+}
+
+/// `prefix` followed by `path`, where `/` under a prefix is the prefix itself
+fn join_path(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    match path {
+        "" | "/" => prefix.to_string(),
+        path => format!("{}{}", prefix, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn handler() -> &'static str {
+        "ok"
+    }
+
+    #[test]
+    fn test_nested_routes_are_recorded() {
+        let keys = ApiRouter::<()>::new()
+            .get("/", handler, "List keys")
+            .delete("/:id/keys/:key_id", handler, "Revoke a key");
+        let api = ApiRouter::new().nest("/api/v1/service-accounts", keys);
+
+        let routes = api.routes();
+        assert_eq!(routes[0].path, "/api/v1/service-accounts");
+        assert_eq!(routes[1].method, Method::DELETE);
+        assert_eq!(routes[1].path_params(), vec!["id", "key_id"]);
+        assert_eq!(routes[1].openapi_path(), "/api/v1/service-accounts/{id}/keys/{key_id}");
+    }
+}
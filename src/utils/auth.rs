@@ -7,21 +7,44 @@
 use axum::http::HeaderMap;
 use uuid::Uuid;
 use anyhow::{Result, anyhow};
-use chrono::{DateTime, Utc, Timelike};
-use std::net::IpAddr;
+use chrono::Utc;
 use std::str::FromStr;
-use base64::{Engine as _, engine::general_purpose};
+use std::sync::OnceLock;
 
 use crate::modules::authorization::{
     RequestContext, ClinicalContext, EmergencyContext, LocationContext, 
-    UrgencyLevel, EmergencyType
+    UrgencyLevel, EmergencyType, PurposeOfUse
 };
+use crate::modules::auth::auth_context::{AuthContext, PURPOSE_OF_USE_HEADER};
+use crate::modules::auth::auth_jwt::token_verifier;
+
+/// Environment variable that, set to `true`, lets development and test
+/// deployments name the acting user with `X-User-ID`. Off by default: the
+/// header is unauthenticated, so anyone could act as any user.
+pub const DEV_USER_HEADER_VAR: &str = "HIMS_DEV_USER_HEADER";
+
+/// Whether `X-User-ID` is honoured, read once from `HIMS_DEV_USER_HEADER`
+pub fn dev_user_header_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        std::env::var(DEV_USER_HEADER_VAR).is_ok_and(|value| value.eq_ignore_ascii_case("true"))
+    })
+}
+
+/// User named by `X-User-ID`, only when `HIMS_DEV_USER_HEADER` is enabled
+pub fn extract_dev_user(headers: &HeaderMap) -> Option<Uuid> {
+    if !dev_user_header_enabled() {
+        return None;
+    }
+    let user_str = headers.get("x-user-id")?.to_str().ok()?;
+    Uuid::from_str(user_str).ok()
+}
 
 /// Extract user ID from HTTP headers
 /// 
 /// This function extracts the user ID from various sources:
 /// - Authorization header (JWT token)
-/// - X-User-ID header (for development/testing)
+/// - X-User-ID header, only with `HIMS_DEV_USER_HEADER=true`
 /// - Session cookies
 pub fn extract_user_from_headers(headers: &HeaderMap) -> Result<Uuid> {
     // Try Authorization header first (JWT)
@@ -29,19 +52,14 @@ pub fn extract_user_from_headers(headers: &HeaderMap) -> Result<Uuid> {
         let auth_str = auth_header.to_str()
             .map_err(|_| anyhow!("Invalid authorization header encoding"))?;
         
-        if auth_str.starts_with("Bearer ") {
-            let token = &auth_str[7..]; // Remove "Bearer " prefix
+        if let Some(token) = auth_str.strip_prefix("Bearer ") {
             return extract_user_from_jwt(token);
         }
     }
     
-    // Try X-User-ID header (for development/testing)
-    if let Some(user_header) = headers.get("x-user-id") {
-        let user_str = user_header.to_str()
-            .map_err(|_| anyhow!("Invalid user ID header encoding"))?;
-        
-        return Uuid::from_str(user_str)
-            .map_err(|_| anyhow!("Invalid user ID format"));
+    // X-User-ID is trusted only in development deployments
+    if let Some(user_id) = extract_dev_user(headers) {
+        return Ok(user_id);
     }
     
     // Try session cookies
@@ -58,40 +76,31 @@ pub fn extract_user_from_headers(headers: &HeaderMap) -> Result<Uuid> {
 }
 
 /// Extract user ID from JWT token
+///
+/// The token's signature, expiry, issuer and audience are verified with the
+/// verifier installed by `AuthService`. Without one, bearer tokens are rejected.
 fn extract_user_from_jwt(token: &str) -> Result<Uuid> {
-    // This is a simplified implementation
-    // In a real application, you would:
-    // 1. Validate the JWT signature
-    // 2. Check expiration
-    // 3. Extract claims
-    
-    // For now, we'll decode the payload (base64 encoded JSON)
-    let parts: Vec<&str> = token.split('.').collect();
-    if parts.len() != 3 {
-        return Err(anyhow!("Invalid JWT format"));
-    }
-    
-    // Decode the payload (second part)
-    let payload = parts[1];
-    let decoded = general_purpose::URL_SAFE_NO_PAD.decode(payload)
-        .map_err(|_| anyhow!("Failed to decode JWT payload"))?;
+    let verifier = token_verifier()
+        .ok_or_else(|| anyhow!("JWT verification is not configured"))?;
     
-    let payload_str = String::from_utf8(decoded)
-        .map_err(|_| anyhow!("Invalid UTF-8 in JWT payload"))?;
+    let claims = verifier.validate_access_token(token)
+        .map_err(|e| anyhow!("Invalid JWT: {}", e))?;
     
-    // Parse JSON to extract user ID
-    let json: serde_json::Value = serde_json::from_str(&payload_str)
-        .map_err(|_| anyhow!("Invalid JSON in JWT payload"))?;
-    
-    let user_id_str = json.get("sub")
-        .or_else(|| json.get("user_id"))
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow!("No user ID found in JWT"))?;
-    
-    Uuid::from_str(user_id_str)
+    Uuid::from_str(&claims.sub)
         .map_err(|_| anyhow!("Invalid user ID format in JWT"))
 }
 
+/// Extract the role claim of a verified bearer token, e.g. `patient`.
+/// Requests without a valid bearer token have no role.
+pub fn extract_role_from_headers(headers: &HeaderMap) -> Option<String> {
+    let token = headers
+        .get("authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    token_verifier()?.validate_access_token(token).ok()?.role
+}
+
 /// Extract user ID from cookies
 fn extract_user_from_cookies(cookie_str: &str) -> Result<Option<Uuid>> {
     for cookie in cookie_str.split(';') {
@@ -124,22 +133,14 @@ fn lookup_session_user(_session_id: &str) -> Result<Option<Uuid>> {
 /// 
 /// This function builds the RequestContext needed for authorization decisions
 /// by gathering information about the user's current session, location, and context.
+/// Where the request came from and in which session are taken from `auth`,
+/// never from headers a client could set.
 pub async fn get_user_session_context(
-    user_id: Uuid,
+    auth: &AuthContext,
     headers: &HeaderMap,
 ) -> Result<RequestContext> {
     let timestamp = Utc::now();
-    
-    // Extract IP address
-    let ip_address = extract_ip_address(headers);
-    
-    // Extract user agent
-    let user_agent = headers.get("user-agent")
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string());
-    
-    // Get session ID
-    let session_id = extract_session_id(headers);
+    let user_id = auth.user_id;
     
     // Check if this is an emergency context
     let emergency_context = check_emergency_context(headers)?;
@@ -151,9 +152,9 @@ pub async fn get_user_session_context(
     let location_context = build_location_context(user_id, headers).await?;
     
     Ok(RequestContext {
-        session_id,
-        ip_address: ip_address.map(|ip| ip.to_string()),
-        user_agent,
+        session_id: auth.session_id.clone(),
+        ip_address: auth.source_ip.clone(),
+        user_agent: auth.user_agent.clone(),
         timestamp,
         location: location_context,
         clinical: clinical_context,
@@ -162,36 +163,22 @@ pub async fn get_user_session_context(
         headers: extract_additional_metadata(headers),
         endpoint: headers.get("x-endpoint").and_then(|h| h.to_str().ok()).map(|s| s.to_string()),
         method: headers.get("x-method").and_then(|h| h.to_str().ok()).map(|s| s.to_string()),
+        risk_score: 0.0,
+        purpose_of_use: auth.purpose_of_use,
+        security_labels: Vec::new(),
+        part2_consent: false,
     })
 }
 
-/// Extract IP address from headers
-fn extract_ip_address(headers: &HeaderMap) -> Option<IpAddr> {
-    // Try various headers in order of preference
-    let ip_headers = [
-        "x-forwarded-for",
-        "x-real-ip",
-        "x-client-ip",
-        "cf-connecting-ip", // Cloudflare
-    ];
-    
-    for header_name in &ip_headers {
-        if let Some(header_value) = headers.get(*header_name) {
-            if let Ok(ip_str) = header_value.to_str() {
-                // X-Forwarded-For can have multiple IPs, take the first one
-                let ip_str = ip_str.split(',').next().unwrap_or(ip_str).trim();
-                if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                    return Some(ip);
-                }
-            }
-        }
-    }
-    
-    None
+/// Extract the declared purpose of use from headers
+pub fn extract_purpose_of_use(headers: &HeaderMap) -> Option<PurposeOfUse> {
+    headers.get(PURPOSE_OF_USE_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(PurposeOfUse::parse)
 }
 
 /// Extract session ID from headers
-fn extract_session_id(headers: &HeaderMap) -> Option<String> {
+pub fn extract_session_id(headers: &HeaderMap) -> Option<String> {
     // Try to get session ID from cookies
     if let Some(cookie_header) = headers.get("cookie") {
         if let Ok(cookie_str) = cookie_header.to_str() {
@@ -210,19 +197,6 @@ fn extract_session_id(headers: &HeaderMap) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-/// Check if MFA is verified for this session
-fn check_mfa_status(headers: &HeaderMap) -> Result<bool> {
-    // Check for MFA verification header
-    if let Some(mfa_header) = headers.get("x-mfa-verified") {
-        let mfa_str = mfa_header.to_str()
-            .map_err(|_| anyhow!("Invalid MFA header encoding"))?;
-        return Ok(mfa_str.to_lowercase() == "true");
-    }
-    
-    // In a real implementation, this would check the session store
-    Ok(false)
-}
-
 /// Check for emergency context in headers
 fn check_emergency_context(headers: &HeaderMap) -> Result<Option<EmergencyContext>> {
     if let Some(emergency_header) = headers.get("x-emergency-access") {
@@ -231,11 +205,6 @@ fn check_emergency_context(headers: &HeaderMap) -> Result<Option<EmergencyContex
         
         if emergency_str.to_lowercase() == "true" {
             // Extract emergency details from other headers
-            let urgency_level = headers.get("x-emergency-urgency")
-                .and_then(|h| h.to_str().ok())
-                .unwrap_or("medium")
-                .to_string();
-            
             let justification = headers.get("x-emergency-justification")
                 .and_then(|h| h.to_str().ok())
                 .map(|s| s.to_string());
@@ -290,72 +259,6 @@ async fn build_location_context(_user_id: Uuid, _headers: &HeaderMap) -> Result<
     Ok(None)
 }
 
-/// Get user's current department and location
-async fn get_user_department_and_location(_user_id: Uuid) -> Result<(Option<Uuid>, Option<Uuid>)> {
-    // This would typically query the user's profile or current session
-    // For now, return None for both
-    Ok((None, None))
-}
-
-/// Get current shift ID for a user
-async fn get_current_shift_id(_user_id: Uuid) -> Result<Option<Uuid>> {
-    // This would query the scheduling system
-    Ok(None)
-}
-
-/// Calculate risk score for the session
-async fn calculate_risk_score(
-    _user_id: Uuid,
-    ip_address: &Option<IpAddr>,
-    user_agent: &Option<String>,
-    clinical_context: &Option<ClinicalContext>,
-) -> Result<f32> {
-    let mut risk_score: f32 = 0.0;
-    
-    // Check for unusual IP address
-    if let Some(ip) = ip_address {
-        if is_unusual_ip_for_user(_user_id, ip).await? {
-            risk_score += 0.3;
-        }
-    }
-    
-    // Check for unusual user agent
-    if let Some(ua) = user_agent {
-        if is_unusual_user_agent_for_user(_user_id, ua).await? {
-            risk_score += 0.2;
-        }
-    }
-    
-    // Check clinical context risks
-    if let Some(clinical) = clinical_context {
-        if clinical.urgency_level >= UrgencyLevel::Emergency {
-            risk_score += 0.1;
-        }
-    }
-    
-    // Check time-based risks (off-hours access)
-    let now = Utc::now();
-    let hour = now.hour();
-    if hour < 6 || hour > 22 {
-        risk_score += 0.1;
-    }
-    
-    // Ensure risk score is between 0.0 and 1.0
-    Ok(risk_score.min(1.0))
-}
-
-/// Check if IP address is unusual for this user
-async fn is_unusual_ip_for_user(_user_id: Uuid, _ip: &IpAddr) -> Result<bool> {
-    // This would check against the user's historical IP addresses
-    Ok(false)
-}
-
-/// Check if user agent is unusual for this user
-async fn is_unusual_user_agent_for_user(_user_id: Uuid, _user_agent: &str) -> Result<bool> {
-    // This would check against the user's historical user agents
-    Ok(false)
-}
-
 /// Extract additional metadata from headers
 fn extract_additional_metadata(headers: &HeaderMap) -> std::collections::HashMap<String, String> {
     let mut metadata = std::collections::HashMap::new();
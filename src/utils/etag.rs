@@ -0,0 +1,78 @@
+// src/utils/etag.rs
+//! FHIR versioning helpers for optimistic concurrency
+//!
+//! Resources carry their version in `meta.version_id`. Reads return it as a
+//! weak ETag (`W/"3"`) and updates must send it back in `If-Match`, so an
+//! update based on a stale read is rejected instead of silently
+//! overwriting a concurrent edit.
+
+use axum::http::{header, HeaderMap, HeaderName, StatusCode};
+use axum::response::Json;
+
+use crate::core::HimsError;
+use crate::models::ResourceMeta;
+
+/// A JSON response carrying the resource's `ETag`
+pub type Versioned<T> = ([(HeaderName, String); 1], Json<T>);
+
+/// The resource's version; resources saved before versioning count as 1
+pub fn version_of(meta: &ResourceMeta) -> &str {
+    meta.version_id.as_deref().unwrap_or("1")
+}
+
+/// The version after `current`; versions are decimal counters starting at 1
+pub fn next_version_id(current: Option<&str>) -> String {
+    let current = current.and_then(|version| version.parse::<u64>().ok()).unwrap_or(0);
+    (current + 1).to_string()
+}
+
+/// Weak ETag for a version, as FHIR servers send it
+pub fn etag(version_id: &str) -> String {
+    format!("W/\"{}\"", version_id)
+}
+
+/// Attach the ETag for `meta` to a response body
+pub fn versioned<T>(meta: &ResourceMeta, body: T) -> Versioned<T> {
+    ([(header::ETAG, etag(version_of(meta)))], Json(body))
+}
+
+/// The version named by `If-Match`, accepting `W/"3"`, `"3"` and `3`
+pub fn if_match_version(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::IF_MATCH)?.to_str().ok()?.trim();
+    let value = value.strip_prefix("W/").unwrap_or(value);
+    let value = value.trim_matches('"');
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// HTTP status for a versioning error, if `error` is one
+pub fn precondition_status(error: &HimsError) -> Option<StatusCode> {
+    match error {
+        HimsError::PreconditionFailed { .. } => Some(StatusCode::PRECONDITION_FAILED),
+        HimsError::ConflictError { .. } => Some(StatusCode::CONFLICT),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_if_match_forms() {
+        for value in ["W/\"3\"", "\"3\"", "3"] {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_MATCH, HeaderValue::from_static(value));
+            assert_eq!(if_match_version(&headers).as_deref(), Some("3"));
+        }
+        assert_eq!(if_match_version(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(etag("3"), "W/\"3\"");
+        assert_eq!(next_version_id(Some("3")), "4");
+        assert_eq!(next_version_id(None), "1");
+        assert_eq!(version_of(&ResourceMeta::default()), "1");
+    }
+}
@@ -0,0 +1,446 @@
+// src/utils/fhir_search.rs
+//! FHIR search parameter parsing
+//!
+//! Shared by resource searches and by conditional create/update/delete,
+//! whose criteria are search parameters (`identifier=system|value`), along
+//! with the pieces of searchset bundles that several resources return.
+
+use chrono::{Months, NaiveDate};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::core::HimsError;
+
+/// A token search parameter: `[system]|[code]`, or a bare `code`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenParam {
+    /// `None` matches any system; `Some("")` (from `|code`) only matches
+    /// values without a system
+    pub system: Option<String>,
+    /// `None` (from `system|`) matches any code in the system
+    pub code: Option<String>,
+}
+
+impl TokenParam {
+    pub fn parse(value: &str) -> Result<Self, HimsError> {
+        let (system, code) = match value.split_once('|') {
+            Some((system, code)) => (Some(system.to_string()), Some(code).filter(|code| !code.is_empty())),
+            None => (None, Some(value).filter(|code| !code.is_empty())),
+        };
+        if code.is_none() && system.as_deref().is_none_or(str::is_empty) {
+            return Err(HimsError::ValidationError {
+                message: format!("Empty token search value: {:?}", value),
+            });
+        }
+        Ok(Self {
+            system,
+            code: code.map(str::to_string),
+        })
+    }
+
+    /// Whether the parameter only matches values without a system
+    pub fn requires_no_system(&self) -> bool {
+        self.system.as_deref() == Some("")
+    }
+}
+
+/// Matching mode of a string search parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringModifier {
+    /// Case-insensitive prefix match, the FHIR default
+    #[default]
+    StartsWith,
+    /// `:contains`, case-insensitive substring match
+    Contains,
+    /// `:exact`, case-sensitive whole-value match
+    Exact,
+}
+
+/// A string search parameter such as `name:contains=smi`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringParam {
+    pub value: String,
+    pub modifier: StringModifier,
+}
+
+impl StringParam {
+    pub fn parse(modifier: Option<&str>, value: &str) -> Result<Self, HimsError> {
+        let modifier = match modifier {
+            None => StringModifier::StartsWith,
+            Some("contains") => StringModifier::Contains,
+            Some("exact") => StringModifier::Exact,
+            Some(other) => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Unsupported string search modifier :{}", other),
+                })
+            }
+        };
+        if value.is_empty() {
+            return Err(HimsError::ValidationError {
+                message: "Empty string search value".to_string(),
+            });
+        }
+        Ok(Self {
+            value: value.to_string(),
+            modifier,
+        })
+    }
+}
+
+/// Comparison prefix of a date search parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatePrefix {
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+}
+
+/// A date search parameter such as `birthdate=ge1990-05`. The value's
+/// precision defines a range: `1990` is all of 1990.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateParam {
+    pub prefix: DatePrefix,
+    /// First day the value covers
+    pub start: NaiveDate,
+    /// Day after the last day the value covers
+    pub end: NaiveDate,
+}
+
+impl DateParam {
+    pub fn parse(value: &str) -> Result<Self, HimsError> {
+        let invalid = || HimsError::ValidationError {
+            message: format!("Invalid date search value: {:?}", value),
+        };
+        let (prefix, date) = match value.get(..2) {
+            Some("eq") => (DatePrefix::Eq, &value[2..]),
+            Some("ne") => (DatePrefix::Ne, &value[2..]),
+            Some("gt") => (DatePrefix::Gt, &value[2..]),
+            Some("lt") => (DatePrefix::Lt, &value[2..]),
+            Some("ge") => (DatePrefix::Ge, &value[2..]),
+            Some("le") => (DatePrefix::Le, &value[2..]),
+            Some(prefix) if prefix.chars().all(|c| c.is_ascii_alphabetic()) => {
+                return Err(HimsError::ValidationError {
+                    message: format!("Unsupported date search prefix {:?}", prefix),
+                })
+            }
+            _ => (DatePrefix::Eq, value),
+        };
+
+        let parts: Vec<&str> = date.split('-').collect();
+        let number = |part: &str, len: usize| -> Result<u32, HimsError> {
+            if part.len() != len || !part.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            part.parse().map_err(|_| invalid())
+        };
+        let (start, end) = match parts.as_slice() {
+            [year] => {
+                let year = number(year, 4)? as i32;
+                (NaiveDate::from_ymd_opt(year, 1, 1), NaiveDate::from_ymd_opt(year + 1, 1, 1))
+            }
+            [year, month] => {
+                let start = NaiveDate::from_ymd_opt(number(year, 4)? as i32, number(month, 2)?, 1);
+                (start, start.and_then(|start| start.checked_add_months(Months::new(1))))
+            }
+            [year, month, day] => {
+                let start = NaiveDate::from_ymd_opt(number(year, 4)? as i32, number(month, 2)?, number(day, 2)?);
+                (start, start.and_then(|start| start.succ_opt()))
+            }
+            _ => (None, None),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(Self { prefix, start, end }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl DateParam {
+    /// Append ` AND <column> ...` matching this parameter's range
+    pub fn push_condition(&self, query: &mut QueryBuilder<'_, Postgres>, column: &str) {
+        match self.prefix {
+            DatePrefix::Eq => {
+                query.push(format!(" AND {} >= ", column)).push_bind(self.start);
+                query.push(format!(" AND {} < ", column)).push_bind(self.end);
+            }
+            DatePrefix::Ne => {
+                query.push(format!(" AND NOT ({} >= ", column)).push_bind(self.start);
+                query.push(format!(" AND {} < ", column)).push_bind(self.end).push(")");
+            }
+            DatePrefix::Gt => {
+                query.push(format!(" AND {} >= ", column)).push_bind(self.end);
+            }
+            DatePrefix::Ge => {
+                query.push(format!(" AND {} >= ", column)).push_bind(self.start);
+            }
+            DatePrefix::Lt => {
+                query.push(format!(" AND {} < ", column)).push_bind(self.start);
+            }
+            DatePrefix::Le => {
+                query.push(format!(" AND {} < ", column)).push_bind(self.end);
+            }
+        }
+    }
+}
+
+/// Whether a search reports the total number of matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotalMode {
+    None,
+    Estimate,
+    Accurate,
+}
+
+impl TotalMode {
+    pub fn parse(value: &str) -> Result<Self, HimsError> {
+        match value {
+            "none" => Ok(TotalMode::None),
+            "estimate" => Ok(TotalMode::Estimate),
+            "accurate" => Ok(TotalMode::Accurate),
+            other => Err(HimsError::ValidationError {
+                message: format!("Invalid _total: {:?}", other),
+            }),
+        }
+    }
+}
+
+/// Parameter names of `_sort` (`_sort=-birthdate,name`), each with whether
+/// it sorts descending
+pub fn parse_sort(value: &str) -> Vec<(String, bool)> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| match key.strip_prefix('-') {
+            Some(key) => (key.to_string(), true),
+            None => (key.to_string(), false),
+        })
+        .collect()
+}
+
+/// A search parameter a resource supports, as the CapabilityStatement
+/// advertises it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SearchParamDefinition {
+    pub name: &'static str,
+    /// FHIR search parameter type: `string`, `token`, `date`, `reference`
+    #[serde(rename = "type")]
+    pub param_type: &'static str,
+    pub documentation: &'static str,
+}
+
+/// Split `name:contains` into the parameter name and its modifier
+pub fn split_modifier(name: &str) -> (&str, Option<&str>) {
+    match name.split_once(':') {
+        Some((name, modifier)) => (name, Some(modifier)),
+        None => (name, None),
+    }
+}
+
+/// A chained parameter such as `patient.name` or `actor:Patient.name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainParam<'a> {
+    /// Reference parameter on the searched resource
+    pub reference: &'a str,
+    /// Target type, when the reference parameter names one
+    pub target: Option<&'a str>,
+    /// Parameter of the referenced resource, with any modifier
+    pub param: &'a str,
+}
+
+/// Split a chained parameter name, or `None` when it is not chained
+pub fn split_chain(name: &str) -> Option<ChainParam<'_>> {
+    let (reference, param) = name.split_once('.')?;
+    let (reference, target) = split_modifier(reference);
+    Some(ChainParam { reference, target, param })
+}
+
+/// An `_include` or `_revinclude` value: `Appointment:actor[:Patient]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeParam {
+    /// Resource type holding the reference
+    pub source: String,
+    /// Reference search parameter
+    pub param: String,
+    /// Only follow references to this type
+    pub target: Option<String>,
+}
+
+impl IncludeParam {
+    pub fn parse(value: &str) -> Result<Self, HimsError> {
+        let mut parts = value.split(':');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(source), Some(param), target, None) if !source.is_empty() && !param.is_empty() => Ok(Self {
+                source: source.to_string(),
+                param: param.to_string(),
+                target: target.map(str::to_string),
+            }),
+            _ => Err(HimsError::ValidationError {
+                message: format!("Invalid include: {:?}", value),
+            }),
+        }
+    }
+}
+
+/// ID in a reference search value, either `Type/{id}` or a bare id
+pub fn reference_id(value: &str, resource_type: &str) -> Result<Uuid, HimsError> {
+    let id = match value.split_once('/') {
+        Some((prefix, id)) if prefix == resource_type => id,
+        Some(_) => {
+            return Err(HimsError::ValidationError {
+                message: format!("Expected a {} reference, got {:?}", resource_type, value),
+            })
+        }
+        None => value,
+    };
+    Uuid::parse_str(id).map_err(|_| HimsError::ValidationError {
+        message: format!("Invalid {} reference: {:?}", resource_type, value),
+    })
+}
+
+/// Why an entry is in a searchset bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchEntryMode {
+    /// Matched the search criteria
+    Match,
+    /// Added by `_include` or `_revinclude`
+    Include,
+}
+
+/// `Bundle.entry.search`
+#[derive(Debug, Clone, Serialize)]
+pub struct EntrySearch {
+    pub mode: SearchEntryMode,
+}
+
+/// A resource added to search results by `_include` or `_revinclude`, as
+/// its stored row
+#[derive(Debug, Clone)]
+pub struct IncludedResource {
+    pub resource_type: String,
+    pub id: Uuid,
+    pub resource: Value,
+}
+
+impl IncludedResource {
+    /// The stored row tagged with its FHIR type
+    pub fn body(self) -> Value {
+        let mut resource = self.resource;
+        if let Value::Object(fields) = &mut resource {
+            fields.insert("resourceType".to_string(), Value::String(self.resource_type));
+        }
+        resource
+    }
+}
+
+/// Escape `%`, `_` and `\` so a value matches literally inside a LIKE pattern
+pub fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Search parameters from a query string, in order and keeping repeats
+/// (`identifier=a&identifier=b` means both)
+pub fn parse_search_query(query: &str) -> Result<Vec<(String, String)>, HimsError> {
+    serde_urlencoded::from_str(query).map_err(|e| HimsError::ValidationError {
+        message: format!("Invalid search parameters: {}", e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_forms() {
+        let full = TokenParam::parse("http://hospital.org/mrn|12345").unwrap();
+        assert_eq!(full.system.as_deref(), Some("http://hospital.org/mrn"));
+        assert_eq!(full.code.as_deref(), Some("12345"));
+
+        let bare = TokenParam::parse("12345").unwrap();
+        assert_eq!(bare.system, None);
+
+        assert!(TokenParam::parse("|12345").unwrap().requires_no_system());
+        assert_eq!(TokenParam::parse("http://hospital.org/mrn|").unwrap().code, None);
+        assert!(TokenParam::parse("|").is_err());
+        assert!(TokenParam::parse("").is_err());
+    }
+
+    #[test]
+    fn test_date_ranges() {
+        let year = DateParam::parse("ge1990").unwrap();
+        assert_eq!(year.prefix, DatePrefix::Ge);
+        assert_eq!(year.start, NaiveDate::from_ymd_opt(1990, 1, 1).unwrap());
+        assert_eq!(year.end, NaiveDate::from_ymd_opt(1991, 1, 1).unwrap());
+
+        let month = DateParam::parse("1990-12").unwrap();
+        assert_eq!(month.prefix, DatePrefix::Eq);
+        assert_eq!(month.end, NaiveDate::from_ymd_opt(1991, 1, 1).unwrap());
+
+        let day = DateParam::parse("lt2000-02-29").unwrap();
+        assert_eq!(day.end, NaiveDate::from_ymd_opt(2000, 3, 1).unwrap());
+
+        assert!(DateParam::parse("ap1990").is_err());
+        assert!(DateParam::parse("1990-13").is_err());
+        assert!(DateParam::parse("90").is_err());
+    }
+
+    #[test]
+    fn test_string_and_sort() {
+        assert_eq!(StringParam::parse(Some("contains"), "smi").unwrap().modifier, StringModifier::Contains);
+        assert!(StringParam::parse(Some("phonetic"), "smith").is_err());
+        assert_eq!(split_modifier("name:exact"), ("name", Some("exact")));
+        assert_eq!(
+            parse_sort("-birthdate, name"),
+            vec![("birthdate".to_string(), true), ("name".to_string(), false)]
+        );
+        assert_eq!(escape_like("50%_a\\b"), "50\\%\\_a\\\\b");
+    }
+
+    #[test]
+    fn test_chains_and_includes() {
+        assert_eq!(
+            split_chain("actor:Patient.name:contains"),
+            Some(ChainParam {
+                reference: "actor",
+                target: Some("Patient"),
+                param: "name:contains",
+            })
+        );
+        assert_eq!(split_chain("name:contains"), None);
+
+        let include = IncludeParam::parse("Appointment:actor:Practitioner").unwrap();
+        assert_eq!(include.param, "actor");
+        assert_eq!(include.target.as_deref(), Some("Practitioner"));
+        assert!(IncludeParam::parse("Appointment").is_err());
+
+        let id = Uuid::new_v4();
+        assert_eq!(reference_id(&format!("Patient/{}", id), "Patient").unwrap(), id);
+        assert_eq!(reference_id(&id.to_string(), "Patient").unwrap(), id);
+        assert!(reference_id(&format!("Practitioner/{}", id), "Patient").is_err());
+    }
+
+    #[test]
+    fn test_search_query_keeps_repeats() {
+        let params = parse_search_query("identifier=a%7C1&identifier=b%7C2").unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("identifier".to_string(), "a|1".to_string()),
+                ("identifier".to_string(), "b|2".to_string()),
+            ]
+        );
+    }
+}
@@ -1,6 +1,14 @@
 // src/utils/mod.rs
 //! Utility modules for common functionality
 
+pub mod api_router;
 pub mod auth;
+pub mod etag;
+pub mod fhir_search;
+pub mod pagination;
 
-pub use auth::*;
\ No newline at end of file
+pub use api_router::*;
+pub use auth::*;
+pub use etag::*;
+pub use fhir_search::*;
+pub use pagination::*;
\ No newline at end of file
@@ -0,0 +1,542 @@
+// src/utils/pagination.rs
+//! Cursor pagination for list endpoints
+//!
+//! Pages are read by keyset rather than offset: a cursor holds the sort
+//! values and id of the row a page continues from, and the next query asks
+//! for rows after it. Pages stay stable while rows are inserted, and a deep
+//! page costs no more than the first. Every ordering ends with `id`, so ties
+//! never make rows repeat or go missing between pages.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use crate::core::HimsError;
+use crate::utils::fhir_search::TotalMode;
+
+/// Environment variable capping the page size of every list endpoint
+pub const MAX_PAGE_SIZE_ENV: &str = "MAX_PAGE_SIZE";
+
+/// Page sizes and total counting of one list endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PagingPolicy {
+    /// Page size unless `_count` says otherwise
+    pub default_count: i64,
+    /// Largest page returned; larger `_count`s are lowered to it
+    pub max_count: i64,
+    /// How the first page counts matches unless `_total` says otherwise.
+    /// Later pages only count when asked, as clients kept the first total.
+    pub default_total: TotalMode,
+}
+
+impl PagingPolicy {
+    pub const fn new(default_count: i64, max_count: i64, default_total: TotalMode) -> Self {
+        Self {
+            default_count,
+            max_count,
+            default_total,
+        }
+    }
+
+    /// This policy under the deployment-wide cap from `MAX_PAGE_SIZE`, if set
+    pub fn configured(self) -> Self {
+        static CAP: OnceLock<Option<i64>> = OnceLock::new();
+        let cap = *CAP.get_or_init(|| {
+            std::env::var(MAX_PAGE_SIZE_ENV)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|cap| *cap > 0)
+        });
+        self.capped(cap)
+    }
+
+    fn capped(self, cap: Option<i64>) -> Self {
+        match cap {
+            Some(cap) => Self {
+                default_count: self.default_count.min(cap),
+                max_count: self.max_count.min(cap),
+                ..self
+            },
+            None => self,
+        }
+    }
+}
+
+/// Which way a cursor pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageDirection {
+    #[serde(rename = "n")]
+    Next,
+    #[serde(rename = "p")]
+    Previous,
+}
+
+/// Position in a result list: the sort values and id of the row a page
+/// continues from. Clients treat it as opaque.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(rename = "d")]
+    pub direction: PageDirection,
+    /// The row's sort key values as text, one per `SortKey`
+    #[serde(rename = "k")]
+    pub key: Vec<Option<String>>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(value: &str) -> Result<Self, HimsError> {
+        let invalid = || HimsError::ValidationError {
+            message: format!("Invalid _cursor: {:?}", value),
+        };
+        let bytes = URL_SAFE_NO_PAD.decode(value).map_err(|_| invalid())?;
+        serde_json::from_slice(&bytes).map_err(|_| invalid())
+    }
+}
+
+/// An ordering of a paged query. Nulls always sort last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    /// SQL expression over the queried table's unqualified columns
+    pub expression: &'static str,
+    /// SQL type the expression's text form is cast back to for comparison
+    pub sql_type: &'static str,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub const fn new(expression: &'static str, sql_type: &'static str, descending: bool) -> Self {
+        Self {
+            expression,
+            sql_type,
+            descending,
+        }
+    }
+}
+
+/// Sort key values and id of a fetched row, from the column `key_column`
+/// selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowKey {
+    pub values: Vec<Option<String>>,
+    pub id: Uuid,
+}
+
+impl RowKey {
+    pub fn from_column(mut column: Vec<Option<String>>) -> Result<Self, HimsError> {
+        let id = column
+            .pop()
+            .flatten()
+            .and_then(|id| Uuid::parse_str(&id).ok())
+            .ok_or_else(|| HimsError::DatabaseError("Page key without a row id".to_string()))?;
+        Ok(Self { values: column, id })
+    }
+
+    fn cursor(&self, direction: PageDirection) -> Cursor {
+        Cursor {
+            direction,
+            key: self.values.clone(),
+            id: self.id,
+        }
+    }
+}
+
+/// Select-list expression holding a row's `keys` and id as `text[]`, to be
+/// read back with `RowKey::from_column`
+pub fn key_column(keys: &[SortKey]) -> String {
+    let mut column = String::from("ARRAY[");
+    for key in keys {
+        column.push_str(&format!("({})::text, ", key.expression));
+    }
+    column.push_str("id::text]");
+    column
+}
+
+/// Append ` ORDER BY` for `keys` then `id`, backwards when `reversed`
+pub fn push_key_order(query: &mut QueryBuilder<'_, Postgres>, keys: &[SortKey], reversed: bool) {
+    query.push(" ORDER BY ");
+    for key in keys {
+        query.push(key.expression);
+        query.push(match (key.descending, reversed) {
+            (true, false) => " DESC NULLS LAST, ",
+            (false, false) => " ASC NULLS LAST, ",
+            (true, true) => " ASC NULLS FIRST, ",
+            (false, true) => " DESC NULLS FIRST, ",
+        });
+    }
+    query.push(if reversed { "id DESC" } else { "id" });
+}
+
+/// Requested page of a list: `_count`, `_cursor` and `_total`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageRequest {
+    pub count: i64,
+    pub cursor: Option<Cursor>,
+    /// `_total`, when given
+    pub total: Option<TotalMode>,
+    policy: PagingPolicy,
+}
+
+impl PageRequest {
+    pub fn new(policy: PagingPolicy) -> Self {
+        let policy = policy.configured();
+        Self {
+            count: policy.default_count,
+            cursor: None,
+            total: None,
+            policy,
+        }
+    }
+
+    /// Apply a `_count`, `_cursor` or `_total` parameter; false for any
+    /// other parameter
+    pub fn apply(&mut self, name: &str, value: &str) -> Result<bool, HimsError> {
+        match name {
+            "_count" => {
+                let count: i64 = value.parse().map_err(|_| HimsError::ValidationError {
+                    message: format!("Invalid value for _count: {:?}", value),
+                })?;
+                self.count = count.clamp(1, self.policy.max_count);
+            }
+            "_cursor" => self.cursor = Some(Cursor::decode(value)?),
+            "_total" => self.total = Some(TotalMode::parse(value)?),
+            "_offset" => {
+                return Err(HimsError::ValidationError {
+                    message: "_offset is not supported; follow the next and previous links, which carry a _cursor"
+                        .to_string(),
+                })
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Reject a cursor from a list sorted another way
+    pub fn check_cursor(&self, keys: &[SortKey]) -> Result<(), HimsError> {
+        match &self.cursor {
+            Some(cursor) if cursor.key.len() != keys.len() => Err(HimsError::ValidationError {
+                message: "_cursor belongs to a search with a different _sort".to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// How to count matches for this page
+    pub fn total(&self) -> TotalMode {
+        match (self.total, &self.cursor) {
+            (Some(total), _) => total,
+            (None, Some(_)) => TotalMode::None,
+            (None, None) => self.policy.default_total,
+        }
+    }
+
+    pub fn direction(&self) -> PageDirection {
+        self.cursor.as_ref().map_or(PageDirection::Next, |cursor| cursor.direction)
+    }
+
+    /// Rows to fetch: one past the page, to tell whether another follows
+    pub fn fetch_limit(&self) -> i64 {
+        self.count + 1
+    }
+
+    /// Append ` AND (...)` keeping rows beyond the cursor, if there is one
+    pub fn push_cursor(&self, query: &mut QueryBuilder<'_, Postgres>, keys: &[SortKey]) {
+        let Some(cursor) = &self.cursor else {
+            return;
+        };
+        let forward = cursor.direction == PageDirection::Next;
+
+        query.push(" AND (");
+        for (i, (key, value)) in keys.iter().zip(&cursor.key).enumerate() {
+            for (equal, value) in keys.iter().zip(&cursor.key).take(i) {
+                Self::push_comparison(query, equal, value, None);
+                query.push(" AND ");
+            }
+            Self::push_comparison(query, key, value, Some(forward));
+            query.push(" OR ");
+        }
+        for (equal, value) in keys.iter().zip(&cursor.key) {
+            Self::push_comparison(query, equal, value, None);
+            query.push(" AND ");
+        }
+        query
+            .push(if forward { "id > " } else { "id < " })
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    /// Append `key` compared with a cursor value: equal to it when `beyond`
+    /// is `None`, otherwise after it (`Some(true)`) or before it in list order
+    fn push_comparison(
+        query: &mut QueryBuilder<'_, Postgres>,
+        key: &SortKey,
+        value: &Option<String>,
+        beyond: Option<bool>,
+    ) {
+        let expression = key.expression;
+        match (value, beyond) {
+            (None, None) => {
+                query.push(format!("{} IS NULL", expression));
+            }
+            // Nulls are last, so nothing follows them
+            (None, Some(true)) => {
+                query.push("false");
+            }
+            (None, Some(false)) => {
+                query.push(format!("{} IS NOT NULL", expression));
+            }
+            (Some(value), None) => {
+                query
+                    .push(format!("{} = ", expression))
+                    .push_bind(value.clone())
+                    .push(format!("::{}", key.sql_type));
+            }
+            (Some(value), Some(after)) => {
+                let operator = if after != key.descending { ">" } else { "<" };
+                query
+                    .push(format!("({} {} ", expression, operator))
+                    .push_bind(value.clone())
+                    .push(format!("::{}", key.sql_type));
+                query.push(if after { format!(" OR {} IS NULL)", expression) } else { ")".to_string() });
+            }
+        }
+    }
+
+    /// Append the order rows are fetched in: list order, or backwards for
+    /// a previous page
+    pub fn push_order(&self, query: &mut QueryBuilder<'_, Postgres>, keys: &[SortKey]) {
+        push_key_order(query, keys, self.direction() == PageDirection::Previous);
+    }
+
+    /// The page within rows fetched in `push_order` order, up to
+    /// `fetch_limit` of them
+    pub fn page<T>(&self, mut rows: Vec<(T, RowKey)>) -> Page<T> {
+        let more = rows.len() as i64 > self.count;
+        rows.truncate(self.count as usize);
+        if self.direction() == PageDirection::Previous {
+            rows.reverse();
+        }
+        self.page_in_order(rows, more)
+    }
+
+    /// The page of at most `count` rows already in list order; `more` when
+    /// the query found another row beyond them
+    pub fn page_in_order<T>(&self, rows: Vec<(T, RowKey)>, more: bool) -> Page<T> {
+        let first = rows.first().map(|(_, key)| key);
+        let last = rows.last().map(|(_, key)| key);
+        let (next, previous) = match self.direction() {
+            PageDirection::Next => (
+                last.filter(|_| more).map(|key| key.cursor(PageDirection::Next)),
+                first.filter(|_| self.cursor.is_some()).map(|key| key.cursor(PageDirection::Previous)),
+            ),
+            PageDirection::Previous => (
+                last.map(|key| key.cursor(PageDirection::Next)),
+                first.filter(|_| more).map(|key| key.cursor(PageDirection::Previous)),
+            ),
+        };
+
+        Page {
+            items: rows.into_iter().map(|(item, _)| item).collect(),
+            next,
+            previous,
+        }
+    }
+}
+
+/// A page of a list with cursors to the pages around it
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<Cursor>,
+    pub previous: Option<Cursor>,
+}
+
+/// Matches of a search across all pages, counted the way `mode` asks:
+/// `count_sql` and `estimate_sql` start the count and query plan, and
+/// `push_filters` completes both
+pub async fn count_matches(
+    pool: &PgPool,
+    mode: TotalMode,
+    count_sql: &str,
+    estimate_sql: &str,
+    push_filters: impl Fn(&mut QueryBuilder<'_, Postgres>),
+) -> Result<Option<i64>, sqlx::Error> {
+    match mode {
+        TotalMode::None => Ok(None),
+        TotalMode::Accurate => {
+            let mut count = QueryBuilder::<Postgres>::new(count_sql);
+            push_filters(&mut count);
+            let total: i64 = count.build_query_scalar().fetch_one(pool).await?;
+            Ok(Some(total))
+        }
+        TotalMode::Estimate => {
+            let mut plan = QueryBuilder::<Postgres>::new(estimate_sql);
+            push_filters(&mut plan);
+            let plan: serde_json::Value = plan.build_query_scalar().fetch_one(pool).await?;
+            Ok(plan[0]["Plan"]["Plan Rows"].as_f64().map(|rows| rows.round() as i64))
+        }
+    }
+}
+
+/// `Bundle.link`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundleLink {
+    pub relation: String,
+    pub url: String,
+}
+
+/// `self`, `next` and `previous` links of a page of `path` listed with
+/// `params`
+pub fn page_links(
+    path: &str,
+    params: &[(String, String)],
+    next: Option<&Cursor>,
+    previous: Option<&Cursor>,
+) -> Vec<BundleLink> {
+    let url = |params: &[(String, String)]| match serde_urlencoded::to_string(params) {
+        Ok(query) if !query.is_empty() => format!("{}?{}", path, query),
+        _ => path.to_string(),
+    };
+    let mut links = vec![BundleLink {
+        relation: "self".to_string(),
+        url: url(params),
+    }];
+
+    for (relation, cursor) in [("next", next), ("previous", previous)] {
+        if let Some(cursor) = cursor {
+            let mut params: Vec<(String, String)> =
+                params.iter().filter(|(name, _)| name != "_cursor").cloned().collect();
+            params.push(("_cursor".to_string(), cursor.encode()));
+            links.push(BundleLink {
+                relation: relation.to_string(),
+                url: url(&params),
+            });
+        }
+    }
+    links
+}
+
+/// `links` as an HTTP `Link` header, for lists that are not bundles
+pub fn link_header(links: &[BundleLink]) -> String {
+    links
+        .iter()
+        .map(|link| format!("<{}>; rel=\"{}\"", link.url, link.relation))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIRTHDATE: SortKey = SortKey::new("birth_date", "date", true);
+    const POLICY: PagingPolicy = PagingPolicy::new(20, 100, TotalMode::Accurate);
+
+    fn key(values: &[Option<&str>]) -> RowKey {
+        RowKey {
+            values: values.iter().map(|value| value.map(str::to_string)).collect(),
+            id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = key(&[Some("1990-01-01"), None]).cursor(PageDirection::Previous);
+        let encoded = cursor.encode();
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(Cursor::decode(&encoded).unwrap(), cursor);
+        assert!(Cursor::decode("not a cursor").is_err());
+    }
+
+    #[test]
+    fn test_page_request_params() {
+        let mut page = PageRequest::new(POLICY);
+        assert!(page.apply("_count", "500").unwrap());
+        assert_eq!(page.count, 100);
+        assert!(!page.apply("name", "smith").unwrap());
+        assert!(page.apply("_offset", "20").is_err());
+        assert_eq!(page.total(), TotalMode::Accurate);
+
+        let cursor = key(&[Some("1990-01-01")]).cursor(PageDirection::Next);
+        page.apply("_cursor", &cursor.encode()).unwrap();
+        assert_eq!(page.total(), TotalMode::None);
+        assert!(page.check_cursor(&[BIRTHDATE]).is_ok());
+        assert!(page.check_cursor(&[]).is_err());
+        page.apply("_total", "estimate").unwrap();
+        assert_eq!(page.total(), TotalMode::Estimate);
+
+        assert_eq!(POLICY.capped(Some(50)).max_count, 50);
+        assert_eq!(POLICY.capped(Some(10)).default_count, 10);
+    }
+
+    #[test]
+    fn test_cursor_sql() {
+        let mut page = PageRequest::new(POLICY);
+        page.cursor = Some(key(&[Some("1990-01-01")]).cursor(PageDirection::Next));
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM patients WHERE true");
+        page.push_cursor(&mut query, &[BIRTHDATE]);
+        page.push_order(&mut query, &[BIRTHDATE]);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM patients WHERE true AND ((birth_date < $1::date OR birth_date IS NULL) \
+             OR birth_date = $2::date AND id > $3) ORDER BY birth_date DESC NULLS LAST, id"
+        );
+
+        page.cursor = Some(key(&[None]).cursor(PageDirection::Previous));
+        let mut query = QueryBuilder::<Postgres>::new("SELECT id FROM patients WHERE true");
+        page.push_cursor(&mut query, &[BIRTHDATE]);
+        page.push_order(&mut query, &[BIRTHDATE]);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM patients WHERE true AND (birth_date IS NOT NULL OR birth_date IS NULL AND id < $1) \
+             ORDER BY birth_date ASC NULLS FIRST, id DESC"
+        );
+        assert_eq!(key_column(&[BIRTHDATE]), "ARRAY[(birth_date)::text, id::text]");
+    }
+
+    #[test]
+    fn test_page_cursors() {
+        let mut page = PageRequest::new(POLICY);
+        page.count = 2;
+        let rows = vec![(1, key(&[])), (2, key(&[])), (3, key(&[]))];
+        let last_id = rows[1].1.id;
+        let first = page.page(rows);
+        assert_eq!(first.items, vec![1, 2]);
+        assert_eq!(first.next.as_ref().map(|cursor| cursor.id), Some(last_id));
+        assert!(first.previous.is_none());
+
+        // Fetched backwards from a cursor, with one more row before them
+        page.cursor = first.next.map(|cursor| Cursor {
+            direction: PageDirection::Previous,
+            ..cursor
+        });
+        let back = page.page(vec![(6, key(&[])), (5, key(&[])), (4, key(&[]))]);
+        assert_eq!(back.items, vec![5, 6]);
+        assert!(back.next.is_some());
+        assert!(back.previous.is_some());
+    }
+
+    #[test]
+    fn test_page_links() {
+        let params = vec![
+            ("name".to_string(), "smith".to_string()),
+            ("_cursor".to_string(), "old".to_string()),
+        ];
+        let next = key(&[]).cursor(PageDirection::Next);
+        let links = page_links("/api/v1/patients", &params, Some(&next), None);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].url, "/api/v1/patients?name=smith&_cursor=old");
+        assert_eq!(links[1].relation, "next");
+        assert_eq!(links[1].url, format!("/api/v1/patients?name=smith&_cursor={}", next.encode()));
+        assert_eq!(
+            link_header(&links[..1]),
+            "</api/v1/patients?name=smith&_cursor=old>; rel=\"self\""
+        );
+        assert_eq!(page_links("/api/v1/audit/logs", &[], None, None)[0].url, "/api/v1/audit/logs");
+    }
+}
@@ -0,0 +1,120 @@
+//! Browser bindings for the standards, so web forms check what the server
+//! will before submitting
+//!
+//! - FHIR validation against the embedded national profile packs
+//! - HL7 v2 parsing
+//! - Code lookup and ValueSet membership in the packs' terminology subsets
+//!
+//! Resources and results cross as JSON text. The functions are plain Rust
+//! off wasm32, so they are tested natively.
+
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::standards::fhir::validators::{FhirValidator, ProfileValidator};
+use crate::standards::fhir::{ProfilePack, ProfileRegistry, ValueSetCode};
+use crate::standards::hl7v2::Hl7Parser;
+
+/// Every embedded profile pack, loaded on first use
+fn registry() -> &'static ProfileRegistry {
+    static REGISTRY: OnceLock<ProfileRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = ProfileRegistry::new();
+        for pack in ProfilePack::ALL {
+            if let Err(e) = pack.load_into(&mut registry) {
+                log::error!("Failed to load {}: {}", pack.package_id(), e);
+            }
+        }
+        registry
+    })
+}
+
+/// Validate a FHIR resource, given as JSON, against the profiles it claims
+/// and, when `country_code` has a pack, the profile that pack requires for
+/// its type. Returns an OperationOutcome as JSON; the resource is valid when
+/// none of its issues is an error.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = validateResource))]
+pub fn validate_resource(resource: &str, country_code: Option<String>) -> String {
+    let parsed = FhirValidator::validate_fhir_json(resource);
+    let outcome = match serde_json::from_str::<Value>(resource) {
+        Ok(resource) if parsed.issue.is_empty() => {
+            let validator = ProfileValidator::new(registry());
+            match country_code.as_deref().and_then(ProfilePack::for_country) {
+                Some(pack) => validator.validate_for_pack(&resource, pack),
+                None => validator.validate_declared(&resource),
+            }
+        }
+        _ => parsed,
+    };
+    serde_json::to_string(&outcome).unwrap_or_default()
+}
+
+/// Parse an HL7 v2 message into JSON of the form
+/// `{"messageType": "ADT^A01", "segments": [{"type": "MSH", "fields": [...]}]}`.
+/// Throws the parse error as a string.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = parseHl7))]
+pub fn parse_hl7(message: &str) -> Result<String, String> {
+    let message = Hl7Parser::new().parse_message(message).map_err(|e| e.to_string())?;
+    let segments: Vec<Value> = message
+        .segments
+        .iter()
+        .map(|segment| json!({ "type": segment.segment_type, "fields": segment.fields }))
+        .collect();
+    Ok(json!({ "messageType": message.message_type, "segments": segments }).to_string())
+}
+
+/// A code of a system as `{"code": ..., "display": ...}` JSON, from the
+/// terminology the profile packs embed; `undefined` when it is not there
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = lookupCode))]
+pub fn lookup_code(system: &str, code: &str) -> Option<String> {
+    let concept = registry().lookup_code(system, code)?;
+    Some(json!({ "code": concept.code, "display": concept.display }).to_string())
+}
+
+/// Whether a ValueSet of the profile packs contains a code; `undefined`
+/// when the ValueSet is not embedded or cannot be expanded offline
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = validateCode))]
+pub fn validate_code(value_set: &str, system: Option<String>, code: &str) -> Option<bool> {
+    let codes = registry().value_set_codes(value_set)?;
+    Some(codes.contains(&ValueSetCode {
+        system,
+        code: code.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standards_bindings() {
+        let outcome: Value = serde_json::from_str(&validate_resource("{\"id\": \"1\"}", None)).unwrap();
+        assert_eq!(outcome["issue"][0]["severity"], "fatal");
+
+        let patient = json!({ "resourceType": "Patient", "gender": "unknown-value" }).to_string();
+        let outcome: Value = serde_json::from_str(&validate_resource(&patient, Some("US".to_string()))).unwrap();
+        assert!(outcome["issue"].as_array().is_some_and(|issues| !issues.is_empty()));
+
+        let parsed: Value = serde_json::from_str(
+            &parse_hl7("MSH|^~\\&|EPIC|HOSPITAL|||20240101||ADT^A01|1|P|2.5\rPID|1||12345").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(parsed["messageType"], "ADT^A01");
+        assert_eq!(parsed["segments"][1]["type"], "PID");
+        assert!(parse_hl7("").is_err());
+
+        let concept: Value =
+            serde_json::from_str(&lookup_code("http://hl7.org/fhir/administrative-gender", "female").unwrap()).unwrap();
+        assert_eq!(concept["display"], "Female");
+        assert!(lookup_code("http://example.org/unknown", "x").is_none());
+
+        let gender = "http://hl7.org/fhir/ValueSet/administrative-gender";
+        let system = Some("http://hl7.org/fhir/administrative-gender".to_string());
+        assert_eq!(validate_code(gender, system.clone(), "male"), Some(true));
+        assert_eq!(validate_code(gender, system, "m"), Some(false));
+        assert_eq!(validate_code("http://example.org/ValueSet/unknown", None, "x"), None);
+    }
+}